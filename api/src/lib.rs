@@ -36,6 +36,9 @@
 pub mod routes;
 pub mod middleware;
 pub mod models;
+pub mod schema_registry;
+pub mod search;
+pub mod webhooks;
 
 use axum::{Router, routing::get};
 use std::sync::Arc;
@@ -50,6 +53,66 @@ pub use models::*;
 pub struct ApiState {
     /// API version
     pub version: String,
+    /// Outbound event schema catalog
+    pub schemas: Arc<schema_registry::SchemaRegistry>,
+    /// Plan entitlements per tenant
+    pub entitlements: Arc<sase_tenant::EntitlementService>,
+    /// Plan-linked API rate limiter
+    pub rate_limiter: Arc<middleware::rate_limit::PlanRateLimiter>,
+    /// Per-user activity reports for insider investigations
+    pub investigations: Arc<sase_ztna::investigation::ActivityReporter>,
+    /// Cross-entity management-plane search
+    pub search: Arc<search::SearchIndex>,
+    /// Webhook subscriptions and delivery tracking
+    pub webhooks: Arc<webhooks::WebhookDelivery>,
+}
+
+impl ApiState {
+    /// Create state with the built-in event catalog
+    pub fn new(version: impl Into<String>) -> Self {
+        Self::with_entitlements(version, Arc::new(sase_tenant::EntitlementService::new()))
+    }
+
+    /// Create state sharing the billing plan entitlement service
+    pub fn with_entitlements(
+        version: impl Into<String>,
+        entitlements: Arc<sase_tenant::EntitlementService>,
+    ) -> Self {
+        let schemas = Arc::new(schema_registry::SchemaRegistry::with_builtin());
+        Self {
+            version: version.into(),
+            webhooks: Arc::new(webhooks::WebhookDelivery::with_registry(schemas.clone())),
+            schemas,
+            rate_limiter: Arc::new(middleware::rate_limit::PlanRateLimiter::new(entitlements.clone())),
+            entitlements,
+            investigations: Arc::new(sase_ztna::investigation::ActivityReporter::new(
+                Arc::new(sase_ztna::audit::AuditLogger::new()),
+            )),
+            search: Arc::new(search::SearchIndex::in_memory().expect("in-memory search index")),
+        }
+    }
+
+    /// Serve reports from the ZTNA gateway's reporter
+    pub fn with_investigations(
+        mut self,
+        investigations: Arc<sase_ztna::investigation::ActivityReporter>,
+    ) -> Self {
+        self.investigations = investigations;
+        self
+    }
+
+    /// Use a webhook engine with a persistent delivery store; it should
+    /// share this state's schema registry
+    pub fn with_webhooks(mut self, webhooks: Arc<webhooks::WebhookDelivery>) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// Use a persistent or shared search index
+    pub fn with_search(mut self, search: Arc<search::SearchIndex>) -> Self {
+        self.search = search;
+        self
+    }
 }
 
 /// OpenAPI documentation
@@ -78,7 +141,7 @@ pub struct ApiState {
     ),
     components(
         schemas(
-            ApiResponse, ErrorResponse, PaginatedResponse,
+            ErrorResponse,
             User, UserCreate, UserRole,
             Policy, PolicyCreate, PolicyAction,
             Site, SiteCreate, SiteStatus,
@@ -99,13 +162,18 @@ pub struct ApiDoc;
 
 /// Build the API router
 pub fn build_router(state: ApiState) -> Router {
+    let rate_limiter = state.rate_limiter.clone();
+
     Router::new()
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .route("/health", get(routes::health::health_check))
         .nest("/api/v1", api_routes())
         .layer(CorsLayer::permissive())
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter,
+            middleware::rate_limit::enforce_plan_limits,
+        ))
         .layer(middleware::auth::auth_layer())
-        .layer(middleware::rate_limit::rate_limit_layer())
         .with_state(Arc::new(state))
 }
 
@@ -118,7 +186,10 @@ fn api_routes() -> Router<Arc<ApiState>> {
         .nest("/tenants/:tenant_id/tunnels", routes::tunnels::router())
         .nest("/tenants/:tenant_id/apps", routes::apps::router())
         .nest("/tenants/:tenant_id/alerts", routes::alerts::router())
+        .nest("/tenants/:tenant_id/tickets", routes::tickets::router())
         .nest("/tenants/:tenant_id/analytics", routes::analytics::router())
+        .nest("/tenants/:tenant_id/investigations", routes::investigations::router())
+        .nest("/tenants/:tenant_id/search", routes::search::router())
         // Global resources
         .nest("/webhooks", routes::webhooks::router())
         .nest("/api-keys", routes::api_keys::router())
@@ -9,8 +9,8 @@ use axum::{
 use tower::Layer;
 
 /// Auth layer (stub - returns identity layer)
-pub fn auth_layer() -> tower::util::Identity {
-    tower::util::Identity::new()
+pub fn auth_layer() -> tower::layer::util::Identity {
+    tower::layer::util::Identity::new()
 }
 
 /// Verify API key
@@ -67,5 +67,6 @@ pub mod scopes {
     pub const WRITE_USERS: &str = "write:users";
     pub const WRITE_POLICIES: &str = "write:policies";
     pub const WRITE_SITES: &str = "write:sites";
+    pub const READ_INVESTIGATIONS: &str = "read:investigations";
     pub const ADMIN: &str = "admin";
 }
@@ -37,6 +37,9 @@ pub enum Permission {
     TunnelsRead,
     TunnelsWrite,
     
+    // Tickets
+    TicketsRead,
+    
     // Webhooks
     WebhooksRead,
     WebhooksWrite,
@@ -71,6 +74,7 @@ impl Permission {
             AlertsRead, AlertsAcknowledge, AlertsResolve,
             AnalyticsRead,
             TunnelsRead, TunnelsWrite,
+            TicketsRead,
             WebhooksRead, WebhooksWrite,
             ApiKeysRead, ApiKeysWrite,
             Admin,
@@ -87,6 +91,7 @@ impl Permission {
             AlertsRead, AlertsAcknowledge, AlertsResolve,
             AnalyticsRead,
             TunnelsRead,
+            TicketsRead,
         ].into_iter().collect()
     }
 
@@ -103,7 +108,7 @@ impl Permission {
         [
             SitesRead, PoliciesRead, AppsRead,
             AlertsRead, AlertsAcknowledge, AlertsResolve,
-            AnalyticsRead, TunnelsRead,
+            AnalyticsRead, TunnelsRead, TicketsRead,
         ].into_iter().collect()
     }
 }
@@ -126,6 +131,8 @@ pub fn parse_scopes(scopes: &[String]) -> HashSet<Permission> {
             "alerts:read" => Some(Permission::AlertsRead),
             "alerts:ack" => Some(Permission::AlertsAcknowledge),
             "analytics:read" => Some(Permission::AnalyticsRead),
+            "tunnels:read" => Some(Permission::TunnelsRead),
+            "tickets:read" => Some(Permission::TicketsRead),
             "admin" => Some(Permission::Admin),
             "read:all" => Some(Permission::SitesRead), // Expand as needed
             _ => None,
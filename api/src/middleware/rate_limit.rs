@@ -1,15 +1,25 @@
 //! Rate limiting middleware
+//!
+//! Tenant requests are limited by the API entitlements of their billing
+//! plan. The same limits are mirrored into Kong consumer plugins by the
+//! gateway, so this layer is the backstop for traffic that bypasses Kong.
+//! Only an authenticated principal selects a tenant's plan; anything else
+//! is limited per client IP.
 
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
 use parking_lot::RwLock;
-use tower::Layer;
-
-/// Rate limit layer (stub - returns identity layer)
-pub fn rate_limit_layer() -> tower::util::Identity {
-    tower::util::Identity::new()
-}
+use sase_tenant::{ApiRateLimits, EntitlementService, SubscriptionTier};
 
 /// Rate limiter
 pub struct RateLimiter {
@@ -110,3 +120,447 @@ pub enum RateLimitResult {
     Allowed { remaining: u32, reset_at: Instant },
     Limited { retry_after: Duration },
 }
+
+/// Per-tenant limiter driven by billing plan entitlements
+pub struct PlanRateLimiter {
+    entitlements: Arc<EntitlementService>,
+    tenants: RwLock<HashMap<String, TenantUsage>>,
+    /// Unauthenticated callers by client address
+    clients: RwLock<HashMap<IpAddr, TenantUsage>>,
+    /// Limits for unauthenticated callers
+    anonymous_limits: ApiRateLimits,
+    /// Entries kept per map before idle ones are evicted
+    max_entries: usize,
+    /// Usage idle this long may be evicted once a map is full
+    idle_ttl: Duration,
+}
+
+/// Limiter state for one tenant or client
+struct TenantUsage {
+    limits: ApiRateLimits,
+    bucket: TokenBucket,
+    minute: UsageWindow,
+    day: UsageWindow,
+    last_seen: Instant,
+}
+
+impl TenantUsage {
+    fn new(limits: &ApiRateLimits, now: Instant) -> Self {
+        Self {
+            bucket: TokenBucket::new(limits.requests_per_second, limits.burst),
+            minute: UsageWindow::new(Duration::from_secs(60)),
+            day: UsageWindow::new(Duration::from_secs(86_400)),
+            limits: limits.clone(),
+            last_seen: now,
+        }
+    }
+}
+
+/// Fixed counting window
+struct UsageWindow {
+    started: Instant,
+    length: Duration,
+    used: u32,
+}
+
+impl UsageWindow {
+    fn new(length: Duration) -> Self {
+        Self { started: Instant::now(), length, used: 0 }
+    }
+
+    fn roll(&mut self, now: Instant) {
+        if now.duration_since(self.started) >= self.length {
+            self.started = now;
+            self.used = 0;
+        }
+    }
+
+    fn resets_in(&self, now: Instant) -> Duration {
+        (self.started + self.length).saturating_duration_since(now)
+    }
+}
+
+/// Which limit rejected a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitWindow {
+    /// Burst over the sustained per-second rate
+    Burst,
+    /// Per-minute quota
+    Minute,
+    /// Daily quota
+    Day,
+}
+
+/// Outcome of a plan rate-limit check
+#[derive(Debug, Clone)]
+pub struct PlanDecision {
+    /// Plan the limits came from
+    pub tier: SubscriptionTier,
+    /// Per-minute quota
+    pub limit: u32,
+    /// Requests left in the current minute
+    pub remaining: u32,
+    /// Time until the minute window resets
+    pub reset_in: Duration,
+    /// Requests used today
+    pub daily_used: u32,
+    /// Daily quota
+    pub daily_limit: u32,
+    /// Set when the request was rejected
+    pub limited_by: Option<LimitWindow>,
+    /// Wait before retrying a rejected request
+    pub retry_after: Duration,
+}
+
+impl PlanDecision {
+    /// Whether the request may proceed
+    pub fn allowed(&self) -> bool {
+        self.limited_by.is_none()
+    }
+}
+
+impl PlanRateLimiter {
+    /// Limiter reading plans from the entitlement service
+    pub fn new(entitlements: Arc<EntitlementService>) -> Self {
+        Self {
+            entitlements,
+            tenants: RwLock::new(HashMap::new()),
+            clients: RwLock::new(HashMap::new()),
+            anonymous_limits: ApiRateLimits::for_tier(&SubscriptionTier::Starter),
+            max_entries: 100_000,
+            idle_ttl: Duration::from_secs(3_600),
+        }
+    }
+
+    /// Limits for unauthenticated callers; Starter limits by default
+    pub fn with_anonymous_limits(mut self, limits: ApiRateLimits) -> Self {
+        self.anonymous_limits = limits;
+        self
+    }
+
+    /// Bound tracked tenants and clients. Once a map holds `max_entries`,
+    /// entries idle for `idle_ttl` are evicted, then the least recently
+    /// seen.
+    pub fn with_capacity(mut self, max_entries: usize, idle_ttl: Duration) -> Self {
+        self.max_entries = max_entries.max(1);
+        self.idle_ttl = idle_ttl;
+        self
+    }
+
+    /// Count a request against the tenant's plan limits. Tenants without
+    /// an assigned plan get Starter limits.
+    pub fn check(&self, tenant_id: &str) -> PlanDecision {
+        let (tier, limits) = match self.entitlements.entitlements(tenant_id) {
+            Some(e) => (e.tier, e.api_limits),
+            None => (SubscriptionTier::Starter, ApiRateLimits::for_tier(&SubscriptionTier::Starter)),
+        };
+        let mut tenants = self.tenants.write();
+        self.count(&mut tenants, tenant_id.to_string(), tier, limits)
+    }
+
+    /// Count an unauthenticated request against its client address
+    pub fn check_client(&self, client: IpAddr) -> PlanDecision {
+        let mut clients = self.clients.write();
+        self.count(&mut clients, client, SubscriptionTier::Starter, self.anonymous_limits.clone())
+    }
+
+    /// Tenants and clients currently tracked
+    pub fn tracked(&self) -> usize {
+        self.tenants.read().len() + self.clients.read().len()
+    }
+
+    fn count<K: Eq + Hash + Clone>(
+        &self,
+        entries: &mut HashMap<K, TenantUsage>,
+        key: K,
+        tier: SubscriptionTier,
+        limits: ApiRateLimits,
+    ) -> PlanDecision {
+        let now = Instant::now();
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            self.evict(entries, now);
+        }
+        let usage = entries.entry(key).or_insert_with(|| TenantUsage::new(&limits, now));
+        usage.last_seen = now;
+
+        // Plan changed: new rates apply immediately, usage carries over
+        if usage.limits != limits {
+            usage.bucket.refill_rate = limits.requests_per_second;
+            usage.bucket.max = limits.burst;
+            usage.bucket.available = usage.bucket.available.min(limits.burst);
+            usage.limits = limits.clone();
+        }
+
+        usage.minute.roll(now);
+        usage.day.roll(now);
+
+        let limited_by = if usage.day.used >= limits.requests_per_day {
+            Some(LimitWindow::Day)
+        } else if usage.minute.used >= limits.requests_per_minute {
+            Some(LimitWindow::Minute)
+        } else if !usage.bucket.try_acquire() {
+            Some(LimitWindow::Burst)
+        } else {
+            usage.minute.used += 1;
+            usage.day.used += 1;
+            None
+        };
+
+        let retry_after = match limited_by {
+            Some(LimitWindow::Day) => usage.day.resets_in(now),
+            Some(LimitWindow::Minute) => usage.minute.resets_in(now),
+            Some(LimitWindow::Burst) => Duration::from_secs(1),
+            None => Duration::ZERO,
+        };
+
+        PlanDecision {
+            tier,
+            limit: limits.requests_per_minute,
+            remaining: limits.requests_per_minute.saturating_sub(usage.minute.used),
+            reset_in: usage.minute.resets_in(now),
+            daily_used: usage.day.used,
+            daily_limit: limits.requests_per_day,
+            limited_by,
+            retry_after,
+        }
+    }
+
+    /// Make room for one entry: drop idle usage, then the least recently
+    /// seen if every entry is active
+    fn evict<K: Eq + Hash + Clone>(&self, entries: &mut HashMap<K, TenantUsage>, now: Instant) {
+        entries.retain(|_, usage| now.duration_since(usage.last_seen) < self.idle_ttl);
+        while entries.len() >= self.max_entries {
+            let Some(oldest) = entries.iter()
+                .min_by_key(|(_, usage)| usage.last_seen)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+    }
+
+    /// Forget a tenant's usage (e.g. after offboarding)
+    pub fn reset(&self, tenant_id: &str) {
+        self.tenants.write().remove(tenant_id);
+    }
+}
+
+/// Middleware enforcing plan limits. Authenticated requests count against
+/// their tenant's plan, everything else against the client address; serve
+/// with `into_make_service_with_connect_info::<SocketAddr>()` or all
+/// unauthenticated callers share one bucket.
+pub async fn enforce_plan_limits(
+    State(limiter): State<Arc<PlanRateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let decision = match request_tenant(&request) {
+        Some(tenant_id) => limiter.check(&tenant_id),
+        None => limiter.check_client(client_addr(&request)),
+    };
+    let mut response = if decision.allowed() {
+        next.run(request).await
+    } else {
+        limited_response(&decision)
+    };
+    apply_headers(&mut response, &decision);
+    response
+}
+
+/// Tenant of the authenticated principal. Path segments are never
+/// trusted: anyone could name another tenant there.
+fn request_tenant(request: &Request) -> Option<String> {
+    if let Some(key) = request.extensions().get::<super::auth::ApiKeyInfo>() {
+        return Some(key.tenant_id.clone());
+    }
+    request.extensions()
+        .get::<super::auth::JwtClaims>()
+        .map(|claims| claims.tenant_id.clone())
+}
+
+fn client_addr(request: &Request) -> IpAddr {
+    request.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+fn limited_response(decision: &PlanDecision) -> Response {
+    let upgrade = decision.tier.upgrade().map(|tier| {
+        let limits = ApiRateLimits::for_tier(&tier);
+        serde_json::json!({
+            "plan": plan_name(&tier),
+            "requests_per_minute": limits.requests_per_minute,
+            "requests_per_day": limits.requests_per_day,
+            "url": "/billing/plans",
+        })
+    });
+
+    let body = serde_json::json!({
+        "success": false,
+        "error": {
+            "code": "rate_limited",
+            "message": format!(
+                "{} plan API limit reached; retry in {}s",
+                plan_name(&decision.tier),
+                decision.retry_after.as_secs().max(1)
+            ),
+        },
+        "rate_limit": {
+            "plan": plan_name(&decision.tier),
+            "window": decision.limited_by,
+            "limit": decision.limit,
+            "remaining": decision.remaining,
+            "daily_used": decision.daily_used,
+            "daily_limit": decision.daily_limit,
+        },
+        "upgrade": upgrade,
+    });
+
+    (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+}
+
+fn apply_headers(response: &mut Response, decision: &PlanDecision) {
+    let headers = response.headers_mut();
+    let mut set = |name: &'static str, value: String| {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    };
+
+    set("x-ratelimit-limit", decision.limit.to_string());
+    set("x-ratelimit-remaining", decision.remaining.to_string());
+    set("x-ratelimit-reset", decision.reset_in.as_secs().to_string());
+    set("x-ratelimit-daily-limit", decision.daily_limit.to_string());
+    set("x-ratelimit-daily-used", decision.daily_used.to_string());
+    set("x-ratelimit-plan", plan_name(&decision.tier).to_string());
+    if !decision.allowed() {
+        set("retry-after", decision.retry_after.as_secs().max(1).to_string());
+        if let Some(tier) = decision.tier.upgrade() {
+            set("x-ratelimit-upgrade", plan_name(&tier).to_string());
+        }
+    }
+}
+
+fn plan_name(tier: &SubscriptionTier) -> &'static str {
+    match tier {
+        SubscriptionTier::Starter => "starter",
+        SubscriptionTier::Business => "business",
+        SubscriptionTier::Enterprise => "enterprise",
+        SubscriptionTier::Custom => "custom",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use axum_test::TestServer;
+    use sase_tenant::Entitlements;
+
+    fn limits(per_minute: u32) -> ApiRateLimits {
+        ApiRateLimits {
+            requests_per_second: 100,
+            burst: 100,
+            requests_per_minute: per_minute,
+            requests_per_day: 1_000,
+        }
+    }
+
+    fn limiter(tenant_per_minute: u32) -> Arc<PlanRateLimiter> {
+        let entitlements = Arc::new(EntitlementService::new());
+        let mut plan = Entitlements::for_tier(SubscriptionTier::Business);
+        plan.api_limits = limits(tenant_per_minute);
+        entitlements.set_entitlements("acme", plan);
+        Arc::new(PlanRateLimiter::new(entitlements).with_anonymous_limits(limits(1)))
+    }
+
+    /// Router with the limiter; `tenant` stands in for the auth layer
+    fn server(limiter: Arc<PlanRateLimiter>, tenant: Option<&'static str>) -> TestServer {
+        let app = Router::new()
+            .route("/tenants/:tenant_id/users", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(limiter, enforce_plan_limits))
+            .layer(axum::middleware::from_fn(move |mut request: Request, next: Next| async move {
+                if let Some(tenant_id) = tenant {
+                    request.extensions_mut().insert(crate::middleware::auth::ApiKeyInfo {
+                        key_id: "key_1".into(),
+                        tenant_id: tenant_id.into(),
+                        scopes: vec![],
+                    });
+                }
+                next.run(request).await
+            }));
+        TestServer::new(app).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_quota_exhausted_returns_429_with_retry_after() {
+        let server = server(limiter(2), Some("acme"));
+
+        for remaining in ["1", "0"] {
+            let response = server.get("/tenants/acme/users").await;
+            response.assert_status_ok();
+            assert_eq!(response.header("x-ratelimit-remaining"), remaining);
+            assert_eq!(response.header("x-ratelimit-plan"), "business");
+        }
+
+        let response = server.get("/tenants/acme/users").await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.header("retry-after").to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after));
+        assert_eq!(response.header("x-ratelimit-upgrade"), "enterprise");
+
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"]["code"], "rate_limited");
+        assert_eq!(body["rate_limit"]["window"], "minute");
+        assert_eq!(body["upgrade"]["plan"], "enterprise");
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_requests_limited_by_client_not_path() {
+        let limiter = limiter(2);
+        let server = server(limiter.clone(), None);
+
+        server.get("/tenants/acme/users").await.assert_status_ok();
+        let response = server.get("/tenants/acme/users").await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.maybe_header("retry-after").is_some());
+
+        // The tenant named in the path was never charged
+        assert_eq!(limiter.check("acme").remaining, 1);
+    }
+
+    #[test]
+    fn test_burst_retry_after() {
+        let entitlements = Arc::new(EntitlementService::new());
+        let mut plan = Entitlements::for_tier(SubscriptionTier::Starter);
+        plan.api_limits = ApiRateLimits { requests_per_second: 1, burst: 1, ..limits(100) };
+        entitlements.set_entitlements("acme", plan);
+        let limiter = PlanRateLimiter::new(entitlements);
+
+        assert!(limiter.check("acme").allowed());
+        let decision = limiter.check("acme");
+        assert_eq!(decision.limited_by, Some(LimitWindow::Burst));
+        assert_eq!(decision.retry_after, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_capacity_evicts_least_recently_seen() {
+        let limiter = PlanRateLimiter::new(Arc::new(EntitlementService::new()))
+            .with_anonymous_limits(limits(5))
+            .with_capacity(2, Duration::from_secs(3_600));
+        let client = |n: u8| IpAddr::V4(Ipv4Addr::new(192, 0, 2, n));
+
+        limiter.check_client(client(1));
+        limiter.check_client(client(1));
+        limiter.check_client(client(2));
+        limiter.check_client(client(3));
+        assert_eq!(limiter.tracked(), 2);
+
+        // Client 1 was evicted and starts over
+        assert_eq!(limiter.check_client(client(1)).remaining, 4);
+        assert_eq!(limiter.tracked(), 2);
+    }
+}
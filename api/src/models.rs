@@ -180,6 +180,48 @@ pub struct TunnelStats {
     pub uptime_seconds: u64,
 }
 
+/// Tunnel creation request
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TunnelCreate {
+    pub name: String,
+    pub tunnel_type: String,
+    pub local_ip: String,
+    pub remote_ip: String,
+}
+
+// ============ Tickets ============
+
+/// Support ticket
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Ticket {
+    pub id: Uuid,
+    pub subject: String,
+    pub description: String,
+    pub status: String,
+    pub priority: String,
+    pub requester: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Ticket creation request
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TicketCreate {
+    pub subject: String,
+    pub description: String,
+    pub priority: String,
+    pub requester: String,
+}
+
+/// Ticket update request
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TicketUpdate {
+    pub subject: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    pub priority: Option<String>,
+}
+
 // ============ Analytics ============
 
 /// Traffic statistics
@@ -224,6 +266,8 @@ pub struct Webhook {
     pub events: Vec<String>,
     pub secret: String,
     pub enabled: bool,
+    /// Pinned schema version per event type
+    pub schema_versions: std::collections::HashMap<String, u32>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -232,6 +276,9 @@ pub struct Webhook {
 pub struct WebhookCreate {
     pub url: String,
     pub events: Vec<String>,
+    /// Pin event types to a schema version; unpinned types get the latest
+    #[serde(default)]
+    pub schema_versions: std::collections::HashMap<String, u32>,
 }
 
 // ============ API Keys ============
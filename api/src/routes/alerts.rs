@@ -1,17 +1,18 @@
 //! Alert management endpoints
 
-use axum::{Router, Json, extract::{Path, Query}};
+use axum::{Router, Json, extract::{Path, Query, State}};
 use axum::routing::{get, post, put};
 use std::sync::Arc;
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use crate::{ApiState, models::*};
+use crate::search::{EntityChange, EntityKind, SearchDocument};
 
 pub fn router() -> Router<Arc<ApiState>> {
     Router::new()
         .route("/", get(list_alerts))
-        .route("/:id", get(get_alert).put(update_alert))
+        .route("/:id", get(get_alert).put(update_alert).delete(delete_alert))
         .route("/:id/acknowledge", post(acknowledge_alert))
         .route("/:id/resolve", post(resolve_alert))
 }
@@ -108,10 +109,11 @@ pub struct AlertUpdate {
 }
 
 pub async fn update_alert(
+    State(state): State<Arc<ApiState>>,
     Path((tenant_id, id)): Path<(Uuid, Uuid)>,
     Json(input): Json<AlertUpdate>,
 ) -> Json<ApiResponse<Alert>> {
-    Json(ApiResponse::success(Alert {
+    let alert = Alert {
         id,
         severity: "high".into(),
         category: "threat".into(),
@@ -123,14 +125,17 @@ pub async fn update_alert(
         created_at: chrono::Utc::now(),
         acknowledged_at: None,
         resolved_at: None,
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::alert(&tenant_id.to_string(), &alert)));
+    Json(ApiResponse::success(alert))
 }
 
 /// Acknowledge alert
 pub async fn acknowledge_alert(
+    State(state): State<Arc<ApiState>>,
     Path((tenant_id, id)): Path<(Uuid, Uuid)>,
 ) -> Json<ApiResponse<Alert>> {
-    Json(ApiResponse::success(Alert {
+    let alert = Alert {
         id,
         severity: "high".into(),
         category: "threat".into(),
@@ -142,14 +147,17 @@ pub async fn acknowledge_alert(
         created_at: chrono::Utc::now(),
         acknowledged_at: Some(chrono::Utc::now()),
         resolved_at: None,
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::alert(&tenant_id.to_string(), &alert)));
+    Json(ApiResponse::success(alert))
 }
 
 /// Resolve alert
 pub async fn resolve_alert(
+    State(state): State<Arc<ApiState>>,
     Path((tenant_id, id)): Path<(Uuid, Uuid)>,
 ) -> Json<ApiResponse<Alert>> {
-    Json(ApiResponse::success(Alert {
+    let alert = Alert {
         id,
         severity: "high".into(),
         category: "threat".into(),
@@ -161,5 +169,20 @@ pub async fn resolve_alert(
         created_at: chrono::Utc::now(),
         acknowledged_at: Some(chrono::Utc::now()),
         resolved_at: Some(chrono::Utc::now()),
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::alert(&tenant_id.to_string(), &alert)));
+    Json(ApiResponse::success(alert))
+}
+
+/// Delete alert
+pub async fn delete_alert(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(Uuid, Uuid)>,
+) -> Json<ApiResponse<()>> {
+    state.search.record(EntityChange::Deleted {
+        tenant_id: tenant_id.to_string(),
+        kind: EntityKind::Alert,
+        id: id.to_string(),
+    });
+    Json(ApiResponse::success(()))
 }
@@ -44,7 +44,7 @@ pub async fn get_traffic_stats(
 #[utoipa::path(
     get,
     path = "/api/v1/analytics/threats",
-    params(("period" = Option<String>, Query)),
+    params(("period" = Option<String>, Query, description = "Time period")),
     responses((status = 200, body = ApiResponse<ThreatStats>)),
     tag = "analytics"
 )]
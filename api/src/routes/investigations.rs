@@ -0,0 +1,181 @@
+//! Insider investigation endpoints
+
+use axum::{Router, Json, Extension, extract::{Path, Query, State}};
+use axum::http::StatusCode;
+use axum::routing::get;
+use std::sync::Arc;
+use sase_ztna::investigation::{ActivityReport, InvestigationError, Investigator, ReportWindow};
+use crate::middleware::auth::{scopes, ApiKeyInfo, JwtClaims};
+use crate::{ApiState, models::*};
+
+/// Investigation routes, nested under `/tenants/{tenant_id}/investigations`
+pub fn router() -> Router<Arc<ApiState>> {
+    Router::new()
+        .route("/users/:user_id/activity", get(get_user_activity))
+}
+
+/// Report window and case reference
+#[derive(serde::Deserialize)]
+pub struct ActivityReportParams {
+    /// RFC 3339 window start
+    pub from: Option<String>,
+    /// RFC 3339 window end, defaults to now
+    pub to: Option<String>,
+    /// Window ending now when `from` is absent: 1h, 24h, 7d, 30d
+    pub period: Option<String>,
+    /// Case the report supports, recorded in the audit trail
+    pub case_id: Option<String>,
+}
+
+type ReportResponse = (StatusCode, Json<ApiResponse<ActivityReport>>);
+
+/// Per-user DNS, web, upload and DLP activity over a window
+pub async fn get_user_activity(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, user_id)): Path<(String, String)>,
+    Query(params): Query<ActivityReportParams>,
+    claims: Option<Extension<JwtClaims>>,
+    api_key: Option<Extension<ApiKeyInfo>>,
+) -> ReportResponse {
+    let (caller_tenant, mut investigator) = match (claims, api_key) {
+        (Some(Extension(claims)), _) => (claims.tenant_id, Investigator {
+            id: claims.sub,
+            roles: claims.roles,
+            case_id: None,
+        }),
+        // Keys carry scopes rather than roles
+        (None, Some(Extension(key))) => {
+            let roles = if key.scopes.iter().any(|s| s == scopes::READ_INVESTIGATIONS) {
+                vec!["investigator".to_string()]
+            } else {
+                Vec::new()
+            };
+            (key.tenant_id, Investigator { id: key.key_id, roles, case_id: None })
+        }
+        (None, None) => return failure(StatusCode::UNAUTHORIZED, "unauthorized", "Authentication required"),
+    };
+    if caller_tenant != tenant_id {
+        return failure(StatusCode::FORBIDDEN, "forbidden", "Tenant mismatch");
+    }
+    investigator.case_id = params.case_id.clone();
+
+    let window = match report_window(&params) {
+        Ok(window) => window,
+        Err(message) => return failure(StatusCode::BAD_REQUEST, "invalid_window", &message),
+    };
+
+    match state.investigations.generate(&investigator, &user_id, window).await {
+        Ok(report) => (StatusCode::OK, Json(ApiResponse::success(report))),
+        Err(e @ InvestigationError::Forbidden(_)) => failure(StatusCode::FORBIDDEN, "forbidden", &e.to_string()),
+        Err(e @ InvestigationError::InvalidWindow(_)) => failure(StatusCode::BAD_REQUEST, "invalid_window", &e.to_string()),
+    }
+}
+
+fn report_window(params: &ActivityReportParams) -> Result<ReportWindow, String> {
+    let parse = |value: &str| chrono::DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .map_err(|_| format!("{} is not an RFC 3339 timestamp", value));
+
+    let to = match &params.to {
+        Some(to) => parse(to)?,
+        None => chrono::Utc::now(),
+    };
+    let from = match (&params.from, params.period.as_deref()) {
+        (Some(from), _) => parse(from)?,
+        (None, None) | (None, Some("24h")) => to - chrono::Duration::hours(24),
+        (None, Some("1h")) => to - chrono::Duration::hours(1),
+        (None, Some("7d")) => to - chrono::Duration::days(7),
+        (None, Some("30d")) => to - chrono::Duration::days(30),
+        (None, Some(other)) => return Err(format!("unknown period {}", other)),
+    };
+
+    Ok(ReportWindow::new(from, to))
+}
+
+fn failure(status: StatusCode, code: &str, message: &str) -> ReportResponse {
+    (status, Json(ApiResponse::error(code, message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(from: Option<&str>, to: Option<&str>, period: Option<&str>) -> ActivityReportParams {
+        ActivityReportParams {
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            period: period.map(str::to_string),
+            case_id: Some("CASE-42".to_string()),
+        }
+    }
+
+    fn claims(tenant_id: &str, roles: &[&str]) -> Option<Extension<JwtClaims>> {
+        Some(Extension(JwtClaims {
+            sub: "inv-7".to_string(),
+            email: "inv@example.com".to_string(),
+            tenant_id: tenant_id.to_string(),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            exp: usize::MAX,
+        }))
+    }
+
+    fn key(scopes: &[&str]) -> Option<Extension<ApiKeyInfo>> {
+        Some(Extension(ApiKeyInfo {
+            key_id: "key-1".to_string(),
+            tenant_id: "t1".to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        }))
+    }
+
+    async fn request(
+        state: &Arc<ApiState>,
+        params: ActivityReportParams,
+        claims: Option<Extension<JwtClaims>>,
+        api_key: Option<Extension<ApiKeyInfo>>,
+    ) -> (StatusCode, Option<String>) {
+        let path = Path(("t1".to_string(), "alice".to_string()));
+        let (status, Json(body)) = get_user_activity(State(state.clone()), path, Query(params), claims, api_key).await;
+        (status, body.error.map(|e| e.code))
+    }
+
+    #[test]
+    fn test_report_window() {
+        let window = report_window(&params(None, Some("2026-10-18T12:00:00Z"), None)).unwrap();
+        assert_eq!(window.from.to_rfc3339(), "2026-10-17T12:00:00+00:00");
+        let window = report_window(&params(None, Some("2026-10-18T12:00:00Z"), Some("7d"))).unwrap();
+        assert_eq!(window.from.to_rfc3339(), "2026-10-11T12:00:00+00:00");
+
+        // An explicit start wins over the period
+        let window = report_window(&params(Some("2026-10-18T09:30:00+02:00"), Some("2026-10-18T12:00:00Z"), Some("1h"))).unwrap();
+        assert_eq!(window.from.to_rfc3339(), "2026-10-18T07:30:00+00:00");
+
+        assert_eq!(
+            report_window(&params(Some("yesterday"), None, None)).unwrap_err(),
+            "yesterday is not an RFC 3339 timestamp"
+        );
+        assert_eq!(report_window(&params(None, None, Some("2w"))).unwrap_err(), "unknown period 2w");
+    }
+
+    #[tokio::test]
+    async fn test_activity_report_access() {
+        let state = Arc::new(ApiState::new("test"));
+        let p = || params(None, None, Some("24h"));
+
+        assert_eq!(request(&state, p(), None, None).await, (StatusCode::UNAUTHORIZED, Some("unauthorized".to_string())));
+        assert_eq!(request(&state, p(), claims("t2", &["investigator"]), None).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(request(&state, p(), claims("t1", &["admin"]), None).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(request(&state, p(), claims("t1", &["investigator"]), None).await, (StatusCode::OK, None));
+
+        // API keys need the investigations scope
+        assert_eq!(request(&state, p(), None, key(&["read:all"])).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(request(&state, p(), None, key(&[scopes::READ_INVESTIGATIONS])).await.0, StatusCode::OK);
+
+        let bad = params(None, None, Some("forever"));
+        assert_eq!(
+            request(&state, bad, claims("t1", &["investigator"]), None).await,
+            (StatusCode::BAD_REQUEST, Some("invalid_window".to_string()))
+        );
+        let inverted = params(Some("2026-10-18T12:00:00Z"), Some("2026-10-18T11:00:00Z"), None);
+        assert_eq!(request(&state, inverted, claims("t1", &["investigator"]), None).await.0, StatusCode::BAD_REQUEST);
+    }
+}
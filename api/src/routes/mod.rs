@@ -7,6 +7,9 @@ pub mod sites;
 pub mod tunnels;
 pub mod apps;
 pub mod alerts;
+pub mod tickets;
 pub mod analytics;
 pub mod webhooks;
 pub mod api_keys;
+pub mod investigations;
+pub mod search;
@@ -1,10 +1,11 @@
 //! Policy management endpoints
 
-use axum::{Router, Json, extract::Path};
+use axum::{Router, Json, extract::{Path, State}};
 use axum::routing::{get, post, put, delete};
 use std::sync::Arc;
 use uuid::Uuid;
 use crate::{ApiState, models::*};
+use crate::search::{EntityChange, EntityKind, SearchDocument};
 
 pub fn router() -> Router<Arc<ApiState>> {
     Router::new()
@@ -83,8 +84,12 @@ pub async fn get_policy(Path(id): Path<Uuid>) -> Json<ApiResponse<Policy>> {
     ),
     tag = "policies"
 )]
-pub async fn create_policy(Json(input): Json<PolicyCreate>) -> Json<ApiResponse<Policy>> {
-    Json(ApiResponse::success(Policy {
+pub async fn create_policy(
+    State(state): State<Arc<ApiState>>,
+    Path(tenant_id): Path<String>,
+    Json(input): Json<PolicyCreate>,
+) -> Json<ApiResponse<Policy>> {
+    let policy = Policy {
         id: Uuid::new_v4(),
         name: input.name,
         description: input.description,
@@ -94,14 +99,17 @@ pub async fn create_policy(Json(input): Json<PolicyCreate>) -> Json<ApiResponse<
         action: input.action,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::policy(&tenant_id, &policy)));
+    Json(ApiResponse::success(policy))
 }
 
 pub async fn update_policy(
-    Path(id): Path<Uuid>,
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
     Json(input): Json<PolicyCreate>,
 ) -> Json<ApiResponse<Policy>> {
-    Json(ApiResponse::success(Policy {
+    let policy = Policy {
         id,
         name: input.name,
         description: input.description,
@@ -111,9 +119,15 @@ pub async fn update_policy(
         action: input.action,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::policy(&tenant_id, &policy)));
+    Json(ApiResponse::success(policy))
 }
 
-pub async fn delete_policy(Path(id): Path<Uuid>) -> Json<ApiResponse<()>> {
+pub async fn delete_policy(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
+) -> Json<ApiResponse<()>> {
+    state.search.record(EntityChange::Deleted { tenant_id, kind: EntityKind::Policy, id: id.to_string() });
     Json(ApiResponse::success(()))
 }
@@ -0,0 +1,145 @@
+//! Management-plane search endpoint
+
+use axum::{Router, Json, Extension, extract::{Path, Query, State}};
+use axum::http::StatusCode;
+use axum::routing::get;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use crate::middleware::auth::{ApiKeyInfo, JwtClaims};
+use crate::middleware::permissions::{parse_scopes, Permission};
+use crate::search::{EntityKind, SearchQuery, SearchResults, MAX_OFFSET};
+use crate::{ApiState, models::*};
+
+/// Search routes, nested under `/tenants/{tenant_id}/search`
+pub fn router() -> Router<Arc<ApiState>> {
+    Router::new()
+        .route("/", get(search))
+}
+
+/// Search query string
+#[derive(serde::Deserialize)]
+pub struct SearchParams {
+    /// Free text; IPs and CIDRs also match exactly
+    pub q: Option<String>,
+    /// Comma-separated kinds: policy, site, user, tunnel, alert, ticket
+    #[serde(rename = "type")]
+    pub kinds: Option<String>,
+    /// Comma-separated `key:value` filters, e.g. `status:open,severity:high`
+    pub filter: Option<String>,
+    /// Page size, 1-100
+    pub limit: Option<usize>,
+    /// Hits to skip, at most 1000
+    pub offset: Option<usize>,
+}
+
+type SearchResponse = (StatusCode, Json<ApiResponse<SearchResults>>);
+
+/// Search a tenant's policies, sites, users, tunnels, alerts and tickets
+pub async fn search(
+    State(state): State<Arc<ApiState>>,
+    Path(tenant_id): Path<String>,
+    Query(params): Query<SearchParams>,
+    claims: Option<Extension<JwtClaims>>,
+    api_key: Option<Extension<ApiKeyInfo>>,
+) -> SearchResponse {
+    let (caller_tenant, permissions) = match (claims, api_key) {
+        (Some(Extension(claims)), _) => {
+            let permissions: HashSet<Permission> = claims.roles.iter()
+                .flat_map(|role| Permission::for_role(role))
+                .collect();
+            (claims.tenant_id, permissions)
+        }
+        (None, Some(Extension(key))) => (key.tenant_id, parse_scopes(&key.scopes)),
+        (None, None) => return failure(StatusCode::UNAUTHORIZED, "unauthorized", "Authentication required"),
+    };
+    if caller_tenant != tenant_id {
+        return failure(StatusCode::FORBIDDEN, "forbidden", "Tenant mismatch");
+    }
+    if EntityKind::readable(&permissions).is_empty() {
+        return failure(StatusCode::FORBIDDEN, "forbidden", "No searchable entities for this caller");
+    }
+
+    let query = match search_query(&params) {
+        Ok(query) => query,
+        Err(message) => return failure(StatusCode::BAD_REQUEST, "invalid_query", &message),
+    };
+
+    match state.search.search(&tenant_id, &permissions, &query) {
+        Ok(results) => (StatusCode::OK, Json(ApiResponse::success(results))),
+        Err(e) => {
+            tracing::error!("Search failed for tenant {}: {}", tenant_id, e);
+            failure(StatusCode::INTERNAL_SERVER_ERROR, "search_failed", "Search is unavailable")
+        }
+    }
+}
+
+fn search_query(params: &SearchParams) -> Result<SearchQuery, String> {
+    let mut kinds = Vec::new();
+    for name in params.kinds.iter().flat_map(|k| k.split(',')).filter(|k| !k.trim().is_empty()) {
+        kinds.push(EntityKind::parse(name).ok_or_else(|| format!("unknown type {}", name.trim()))?);
+    }
+
+    let mut filters = BTreeMap::new();
+    for filter in params.filter.iter().flat_map(|f| f.split(',')).filter(|f| !f.trim().is_empty()) {
+        let (key, value) = filter.split_once(':')
+            .ok_or_else(|| format!("filter {} must be key:value", filter.trim()))?;
+        filters.insert(key.trim().to_string(), value.trim().to_string());
+    }
+
+    let offset = params.offset.unwrap_or(0);
+    if offset > MAX_OFFSET {
+        return Err(format!("offset must be at most {}", MAX_OFFSET));
+    }
+
+    Ok(SearchQuery {
+        text: params.q.clone(),
+        kinds,
+        filters,
+        limit: params.limit.unwrap_or(20),
+        offset,
+    })
+}
+
+fn failure(status: StatusCode, code: &str, message: &str) -> SearchResponse {
+    (status, Json(ApiResponse::error(code, message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(offset: Option<usize>) -> SearchParams {
+        SearchParams {
+            q: Some("finance".to_string()),
+            kinds: Some("policy, tickets".to_string()),
+            filter: Some("status:open".to_string()),
+            limit: None,
+            offset,
+        }
+    }
+
+    #[test]
+    fn test_query_from_params() {
+        let query = search_query(&params(Some(40))).unwrap();
+        assert_eq!(query.kinds, vec![EntityKind::Policy, EntityKind::Ticket]);
+        assert_eq!(query.filters.get("status").map(String::as_str), Some("open"));
+        assert_eq!((query.limit, query.offset), (20, 40));
+    }
+
+    #[test]
+    fn test_offset_over_maximum_rejected() {
+        assert!(search_query(&params(Some(MAX_OFFSET))).is_ok());
+        assert!(search_query(&params(Some(MAX_OFFSET + 1))).is_err());
+    }
+
+    #[test]
+    fn test_bad_kind_and_filter_rejected() {
+        let mut bad_kind = params(None);
+        bad_kind.kinds = Some("widgets".to_string());
+        assert!(search_query(&bad_kind).is_err());
+
+        let mut bad_filter = params(None);
+        bad_filter.filter = Some("status".to_string());
+        assert!(search_query(&bad_filter).is_err());
+    }
+}
@@ -1,10 +1,11 @@
 //! Site management endpoints
 
-use axum::{Router, Json, extract::Path};
+use axum::{Router, Json, extract::{Path, State}};
 use axum::routing::{get, post};
 use std::sync::Arc;
 use uuid::Uuid;
 use crate::{ApiState, models::*};
+use crate::search::{EntityChange, SearchDocument};
 
 pub fn router() -> Router<Arc<ApiState>> {
     Router::new()
@@ -40,7 +41,7 @@ pub async fn list_sites() -> Json<ApiResponse<PaginatedResponse<Site>>> {
 #[utoipa::path(
     get,
     path = "/api/v1/sites/{id}",
-    params(("id" = Uuid, Path)),
+    params(("id" = Uuid, Path, description = "Site ID")),
     responses((status = 200, body = ApiResponse<Site>)),
     tag = "sites"
 )]
@@ -56,8 +57,12 @@ pub async fn get_site(Path(id): Path<Uuid>) -> Json<ApiResponse<Site>> {
     }))
 }
 
-pub async fn create_site(Json(input): Json<SiteCreate>) -> Json<ApiResponse<Site>> {
-    Json(ApiResponse::success(Site {
+pub async fn create_site(
+    State(state): State<Arc<ApiState>>,
+    Path(tenant_id): Path<String>,
+    Json(input): Json<SiteCreate>,
+) -> Json<ApiResponse<Site>> {
+    let site = Site {
         id: Uuid::new_v4(),
         name: input.name,
         location: input.location,
@@ -65,5 +70,7 @@ pub async fn create_site(Json(input): Json<SiteCreate>) -> Json<ApiResponse<Site
         edge_count: 0,
         user_count: 0,
         created_at: chrono::Utc::now(),
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::site(&tenant_id, &site)));
+    Json(ApiResponse::success(site))
 }
@@ -0,0 +1,98 @@
+//! Support ticket endpoints
+
+use axum::{Router, Json, extract::{Path, State}};
+use axum::routing::get;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::{ApiState, models::*};
+use crate::search::{EntityChange, EntityKind, SearchDocument};
+
+pub fn router() -> Router<Arc<ApiState>> {
+    Router::new()
+        .route("/", get(list_tickets).post(create_ticket))
+        .route("/:id", get(get_ticket).put(update_ticket).delete(delete_ticket))
+}
+
+/// List support tickets
+pub async fn list_tickets() -> Json<ApiResponse<PaginatedResponse<Ticket>>> {
+    Json(ApiResponse::success(PaginatedResponse {
+        items: vec![
+            Ticket {
+                id: Uuid::new_v4(),
+                subject: "Branch tunnel flapping".into(),
+                description: "HQ-to-PoP1 drops every few minutes since the ISP change".into(),
+                status: "open".into(),
+                priority: "high".into(),
+                requester: "netops@example.com".into(),
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            },
+        ],
+        total: 1, page: 1, per_page: 20, total_pages: 1,
+    }))
+}
+
+/// Get ticket by ID
+pub async fn get_ticket(
+    Path((_tenant_id, id)): Path<(String, Uuid)>,
+) -> Json<ApiResponse<Ticket>> {
+    Json(ApiResponse::success(Ticket {
+        id,
+        subject: "Branch tunnel flapping".into(),
+        description: "HQ-to-PoP1 drops every few minutes since the ISP change".into(),
+        status: "open".into(),
+        priority: "high".into(),
+        requester: "netops@example.com".into(),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    }))
+}
+
+/// Open a support ticket
+pub async fn create_ticket(
+    State(state): State<Arc<ApiState>>,
+    Path(tenant_id): Path<String>,
+    Json(input): Json<TicketCreate>,
+) -> Json<ApiResponse<Ticket>> {
+    let ticket = Ticket {
+        id: Uuid::new_v4(),
+        subject: input.subject,
+        description: input.description,
+        status: "open".into(),
+        priority: input.priority,
+        requester: input.requester,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::ticket(&tenant_id, &ticket)));
+    Json(ApiResponse::success(ticket))
+}
+
+/// Update a support ticket
+pub async fn update_ticket(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
+    Json(input): Json<TicketUpdate>,
+) -> Json<ApiResponse<Ticket>> {
+    let ticket = Ticket {
+        id,
+        subject: input.subject.unwrap_or_else(|| "Ticket".into()),
+        description: input.description.unwrap_or_default(),
+        status: input.status.unwrap_or_else(|| "open".into()),
+        priority: input.priority.unwrap_or_else(|| "normal".into()),
+        requester: String::new(),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::ticket(&tenant_id, &ticket)));
+    Json(ApiResponse::success(ticket))
+}
+
+/// Delete a support ticket
+pub async fn delete_ticket(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
+) -> Json<ApiResponse<()>> {
+    state.search.record(EntityChange::Deleted { tenant_id, kind: EntityKind::Ticket, id: id.to_string() });
+    Json(ApiResponse::success(()))
+}
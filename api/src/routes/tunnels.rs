@@ -1,14 +1,16 @@
 //! Tunnel management endpoints
 
-use axum::{Router, Json, extract::Path};
-use axum::routing::get;
+use axum::{Router, Json, extract::{Path, State}};
+use axum::routing::{get, put};
 use std::sync::Arc;
 use uuid::Uuid;
 use crate::{ApiState, models::*};
+use crate::search::{EntityChange, EntityKind, SearchDocument};
 
 pub fn router() -> Router<Arc<ApiState>> {
     Router::new()
-        .route("/", get(list_tunnels))
+        .route("/", get(list_tunnels).post(create_tunnel))
+        .route("/:id", put(update_tunnel).delete(delete_tunnel))
         .route("/:id/stats", get(get_tunnel_stats))
 }
 
@@ -40,7 +42,7 @@ pub async fn list_tunnels() -> Json<ApiResponse<PaginatedResponse<Tunnel>>> {
 #[utoipa::path(
     get,
     path = "/api/v1/tunnels/{id}/stats",
-    params(("id" = Uuid, Path)),
+    params(("id" = Uuid, Path, description = "Tunnel ID")),
     responses((status = 200, body = ApiResponse<TunnelStats>)),
     tag = "tunnels"
 )]
@@ -55,3 +57,50 @@ pub async fn get_tunnel_stats(Path(id): Path<Uuid>) -> Json<ApiResponse<TunnelSt
         uptime_seconds: 86400,
     }))
 }
+
+/// Create a tunnel
+pub async fn create_tunnel(
+    State(state): State<Arc<ApiState>>,
+    Path(tenant_id): Path<String>,
+    Json(input): Json<TunnelCreate>,
+) -> Json<ApiResponse<Tunnel>> {
+    let tunnel = Tunnel {
+        id: Uuid::new_v4(),
+        name: input.name,
+        tunnel_type: input.tunnel_type,
+        status: "provisioning".into(),
+        local_ip: input.local_ip,
+        remote_ip: input.remote_ip,
+        created_at: chrono::Utc::now(),
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::tunnel(&tenant_id, &tunnel)));
+    Json(ApiResponse::success(tunnel))
+}
+
+/// Update a tunnel
+pub async fn update_tunnel(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
+    Json(input): Json<TunnelCreate>,
+) -> Json<ApiResponse<Tunnel>> {
+    let tunnel = Tunnel {
+        id,
+        name: input.name,
+        tunnel_type: input.tunnel_type,
+        status: "up".into(),
+        local_ip: input.local_ip,
+        remote_ip: input.remote_ip,
+        created_at: chrono::Utc::now(),
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::tunnel(&tenant_id, &tunnel)));
+    Json(ApiResponse::success(tunnel))
+}
+
+/// Delete a tunnel
+pub async fn delete_tunnel(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
+) -> Json<ApiResponse<()>> {
+    state.search.record(EntityChange::Deleted { tenant_id, kind: EntityKind::Tunnel, id: id.to_string() });
+    Json(ApiResponse::success(()))
+}
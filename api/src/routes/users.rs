@@ -5,6 +5,7 @@ use axum::routing::{get, post, put, delete};
 use std::sync::Arc;
 use uuid::Uuid;
 use crate::{ApiState, models::*};
+use crate::search::{EntityChange, EntityKind, SearchDocument};
 
 pub fn router() -> Router<Arc<ApiState>> {
     Router::new()
@@ -98,8 +99,12 @@ pub async fn get_user(Path(id): Path<Uuid>) -> Json<ApiResponse<User>> {
     ),
     tag = "users"
 )]
-pub async fn create_user(Json(input): Json<UserCreate>) -> Json<ApiResponse<User>> {
-    Json(ApiResponse::success(User {
+pub async fn create_user(
+    State(state): State<Arc<ApiState>>,
+    Path(tenant_id): Path<String>,
+    Json(input): Json<UserCreate>,
+) -> Json<ApiResponse<User>> {
+    let user = User {
         id: Uuid::new_v4(),
         email: input.email,
         name: input.name,
@@ -108,14 +113,17 @@ pub async fn create_user(Json(input): Json<UserCreate>) -> Json<ApiResponse<User
         status: "pending".into(),
         created_at: chrono::Utc::now(),
         last_login: None,
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::user(&tenant_id, &user)));
+    Json(ApiResponse::success(user))
 }
 
 pub async fn update_user(
-    Path(id): Path<Uuid>,
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
     Json(input): Json<UserCreate>,
 ) -> Json<ApiResponse<User>> {
-    Json(ApiResponse::success(User {
+    let user = User {
         id,
         email: input.email,
         name: input.name,
@@ -124,9 +132,15 @@ pub async fn update_user(
         status: "active".into(),
         created_at: chrono::Utc::now(),
         last_login: None,
-    }))
+    };
+    state.search.record(EntityChange::Upserted(SearchDocument::user(&tenant_id, &user)));
+    Json(ApiResponse::success(user))
 }
 
-pub async fn delete_user(Path(id): Path<Uuid>) -> Json<ApiResponse<()>> {
+pub async fn delete_user(
+    State(state): State<Arc<ApiState>>,
+    Path((tenant_id, id)): Path<(String, Uuid)>,
+) -> Json<ApiResponse<()>> {
+    state.search.record(EntityChange::Deleted { tenant_id, kind: EntityKind::User, id: id.to_string() });
     Json(ApiResponse::success(()))
 }
@@ -1,53 +1,215 @@
 //! Webhook management endpoints
 
-use axum::{Router, Json, extract::Path};
+use axum::{Router, Json, extract::{Path, Query, State}};
+use axum::http::StatusCode;
 use axum::routing::{get, post, delete};
 use std::sync::Arc;
 use uuid::Uuid;
+use serde::Deserialize;
 use crate::{ApiState, models::*};
+use crate::schema_registry::{EventDefinition, EventTypeSummary, SchemaError, SchemaVersion};
+use crate::webhooks::{DeliveryRecord, DeliveryStatus, EventType, RetryPolicy, WebhookConfig, WebhookError};
 
 pub fn router() -> Router<Arc<ApiState>> {
     Router::new()
         .route("/", get(list_webhooks).post(create_webhook))
         .route("/:id", delete(delete_webhook))
         .route("/:id/test", post(test_webhook))
+        .route("/:id/deliveries", get(list_deliveries))
+        .route("/:id/deliveries/:delivery_id", get(get_delivery))
+        .route("/:id/deliveries/:delivery_id/replay", post(replay_delivery))
+        .route("/:id/replay-failed", post(replay_failed))
+        .route("/event-types", get(list_event_types))
+        .route("/event-types/:event_type", get(get_event_type))
+        .route("/event-types/:event_type/versions/:version", get(get_event_schema))
+        .route("/event-types/:event_type/versions/:version/deprecate", post(deprecate_event_schema))
 }
 
-pub async fn list_webhooks() -> Json<ApiResponse<Vec<Webhook>>> {
-    Json(ApiResponse::success(vec![
-        Webhook {
-            id: Uuid::new_v4(),
-            url: "https://example.com/webhook".into(),
-            events: vec!["threat.detected".into(), "policy.changed".into()],
-            secret: "whsec_****".into(),
-            enabled: true,
-            created_at: chrono::Utc::now(),
-        },
-    ]))
+fn to_model(config: &WebhookConfig, secret: String) -> Webhook {
+    Webhook {
+        id: config.id,
+        url: config.url.clone(),
+        events: config.events.iter().map(|e| e.to_string()).collect(),
+        secret,
+        enabled: config.enabled,
+        schema_versions: config.schema_versions.clone(),
+        created_at: chrono::Utc::now(),
+    }
 }
 
-pub async fn create_webhook(Json(input): Json<WebhookCreate>) -> Json<ApiResponse<Webhook>> {
+pub async fn list_webhooks(State(state): State<Arc<ApiState>>) -> Json<ApiResponse<Vec<Webhook>>> {
+    Json(ApiResponse::success(
+        state.webhooks.subscriptions()
+            .iter()
+            .map(|c| to_model(c, "whsec_****".into()))
+            .collect(),
+    ))
+}
+
+pub async fn create_webhook(
+    State(state): State<Arc<ApiState>>,
+    Json(input): Json<WebhookCreate>,
+) -> Json<ApiResponse<Webhook>> {
+    let mut events = Vec::with_capacity(input.events.len());
+    for event in &input.events {
+        match event.parse::<EventType>() {
+            Ok(event_type) => events.push(event_type),
+            Err(e) => return Json(ApiResponse::error("invalid_event_type", &e)),
+        }
+    }
+
     let secret = format!("whsec_{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-    Json(ApiResponse::success(Webhook {
+    let config = WebhookConfig {
         id: Uuid::new_v4(),
         url: input.url,
-        events: input.events,
-        secret,
+        events,
+        secret: secret.clone(),
+        retry_policy: RetryPolicy::default(),
         enabled: true,
-        created_at: chrono::Utc::now(),
-    }))
+        schema_versions: input.schema_versions,
+    };
+
+    match state.webhooks.subscribe(config.clone()) {
+        // The secret is only revealed at creation
+        Ok(_) => Json(ApiResponse::success(to_model(&config, secret))),
+        Err(e) => Json(ApiResponse::error("invalid_schema_version", &e.to_string())),
+    }
+}
+
+pub async fn delete_webhook(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<()>> {
+    match state.webhooks.unsubscribe(id) {
+        Some(_) => Json(ApiResponse::success(())),
+        None => Json(ApiResponse::error("not_found", "Webhook not found")),
+    }
+}
+
+pub async fn test_webhook(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<WebhookTestResult>> {
+    match state.webhooks.send_test(id).await {
+        Ok(attempt) => Json(ApiResponse::success(WebhookTestResult {
+            success: attempt.error.is_none(),
+            status_code: attempt.status_code.unwrap_or(0),
+            response_time_ms: attempt.duration_ms as u32,
+            error: attempt.error,
+        })),
+        Err(e) => Json(ApiResponse::error("not_found", &e.to_string())),
+    }
+}
+
+/// Delivery list filter
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    /// pending, succeeded or failed
+    pub status: Option<DeliveryStatus>,
+}
+
+/// Deliveries for a webhook, newest first
+pub async fn list_deliveries(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DeliveryQuery>,
+) -> Json<ApiResponse<Vec<DeliveryRecord>>> {
+    if state.webhooks.subscription(id).is_none() {
+        return Json(ApiResponse::error("not_found", "Webhook not found"));
+    }
+    Json(ApiResponse::success(state.webhooks.deliveries(id, query.status)))
+}
+
+/// One delivery with its attempt history
+pub async fn get_delivery(
+    State(state): State<Arc<ApiState>>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Json<ApiResponse<DeliveryRecord>> {
+    match state.webhooks.delivery(delivery_id) {
+        Some(record) if record.subscription_id == id => Json(ApiResponse::success(record)),
+        _ => Json(ApiResponse::error("not_found", "Delivery not found")),
+    }
 }
 
-pub async fn delete_webhook(Path(_id): Path<Uuid>) -> Json<ApiResponse<()>> {
-    Json(ApiResponse::success(()))
+/// Queue a fresh delivery of a finished delivery's payload
+pub async fn replay_delivery(
+    State(state): State<Arc<ApiState>>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Json<ApiResponse<DeliveryRecord>> {
+    if state.webhooks.delivery(delivery_id).is_none_or(|r| r.subscription_id != id) {
+        return Json(ApiResponse::error("not_found", "Delivery not found"));
+    }
+    match state.webhooks.replay(delivery_id) {
+        Ok(record) => Json(ApiResponse::success(record)),
+        Err(e @ WebhookError::StillPending(_)) => Json(ApiResponse::error("conflict", &e.to_string())),
+        Err(e) => Json(ApiResponse::error("not_found", &e.to_string())),
+    }
 }
 
-pub async fn test_webhook(Path(_id): Path<Uuid>) -> Json<ApiResponse<WebhookTestResult>> {
-    Json(ApiResponse::success(WebhookTestResult {
-        success: true,
-        status_code: 200,
-        response_time_ms: 150,
-    }))
+/// Replay every failed delivery not already replayed
+pub async fn replay_failed(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<Uuid>,
+) -> Json<ApiResponse<Vec<DeliveryRecord>>> {
+    match state.webhooks.replay_failed(id) {
+        Ok(records) => Json(ApiResponse::success(records)),
+        Err(e) => Json(ApiResponse::error("not_found", &e.to_string())),
+    }
+}
+
+/// Event catalog
+pub async fn list_event_types(State(state): State<Arc<ApiState>>) -> Json<ApiResponse<Vec<EventTypeSummary>>> {
+    Json(ApiResponse::success(state.schemas.catalog()))
+}
+
+/// Event type with all schema versions
+pub async fn get_event_type(
+    State(state): State<Arc<ApiState>>,
+    Path(event_type): Path<String>,
+) -> Json<ApiResponse<EventDefinition>> {
+    match state.schemas.get(&event_type) {
+        Some(def) => Json(ApiResponse::success(def)),
+        None => Json(ApiResponse::error("not_found", "Unknown event type")),
+    }
+}
+
+/// One versioned JSON Schema
+pub async fn get_event_schema(
+    State(state): State<Arc<ApiState>>,
+    Path((event_type, version)): Path<(String, u32)>,
+) -> Json<ApiResponse<SchemaVersion>> {
+    match state.schemas.schema(&event_type, version) {
+        Some(schema) => Json(ApiResponse::success(schema)),
+        None => Json(ApiResponse::error("not_found", "Unknown event type or version")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeprecateRequest {
+    pub sunset_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Start a deprecation period; pinned subscribers get dual-emit until sunset.
+/// Only a current version can be deprecated; anything else is a 409.
+pub async fn deprecate_event_schema(
+    State(state): State<Arc<ApiState>>,
+    Path((event_type, version)): Path<(String, u32)>,
+    Json(input): Json<DeprecateRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    if state.schemas.get(&event_type).and_then(|d| d.latest()) == Some(version) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("invalid_request", "Cannot deprecate the latest version")),
+        );
+    }
+
+    match state.schemas.deprecate(&event_type, version, input.sunset_at) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::success(()))),
+        Err(e @ SchemaError::NotCurrent(..)) => {
+            (StatusCode::CONFLICT, Json(ApiResponse::error("conflict", &e.to_string())))
+        }
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error("not_found", &e.to_string()))),
+    }
 }
 
 #[derive(serde::Serialize)]
@@ -55,4 +217,29 @@ pub struct WebhookTestResult {
     success: bool,
     status_code: u16,
     response_time_ms: u32,
+    error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn deprecate(state: &Arc<ApiState>, version: u32) -> StatusCode {
+        let input = DeprecateRequest { sunset_at: chrono::Utc::now() + chrono::Duration::days(30) };
+        let path = Path(("alert.created".to_string(), version));
+        deprecate_event_schema(State(state.clone()), path, Json(input)).await.0
+    }
+
+    #[tokio::test]
+    async fn test_deprecate_only_current_versions() {
+        let state = Arc::new(ApiState::new("test"));
+
+        assert_eq!(deprecate(&state, 2).await, StatusCode::BAD_REQUEST);
+        assert_eq!(deprecate(&state, 9).await, StatusCode::NOT_FOUND);
+        assert_eq!(deprecate(&state, 1).await, StatusCode::OK);
+        assert_eq!(deprecate(&state, 1).await, StatusCode::CONFLICT);
+
+        state.schemas.retire("alert.created", 1).unwrap();
+        assert_eq!(deprecate(&state, 1).await, StatusCode::CONFLICT);
+    }
 }
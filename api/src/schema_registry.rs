@@ -0,0 +1,634 @@
+//! Webhook Event Schema Registry
+//!
+//! Versioned JSON Schemas for every outbound event type. Producers emit
+//! payloads in the latest version; older versions are derived by chaining
+//! per-version downgrade functions. Subscriptions pin a version per event
+//! type, and a deprecated version keeps being delivered alongside its
+//! successor (dual-emit) until its sunset date.
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// Converts a payload of the next version down to this one
+pub type Downgrade = fn(Value) -> Value;
+
+/// Schema registry errors
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    /// Event type not in the catalog
+    #[error("unknown event type: {0}")]
+    UnknownEvent(String),
+    /// Version not registered for the event type
+    #[error("unknown schema version {1} for {0}")]
+    UnknownVersion(String, u32),
+    /// Version has passed its sunset date
+    #[error("schema version {1} for {0} is retired")]
+    Retired(String, u32),
+    /// Versions must be registered in increasing order
+    #[error("schema version {1} for {0} must be newer than {2}")]
+    OutOfOrder(String, u32, u32),
+    /// Only current versions can start a deprecation period
+    #[error("schema version {1} for {0} is {2}, not current")]
+    NotCurrent(String, u32, &'static str),
+    /// Payload does not conform to the schema
+    #[error("payload does not match {event} v{version}: {}", .errors.join("; "))]
+    Invalid {
+        /// Event type
+        event: String,
+        /// Schema version
+        version: u32,
+        /// Validation failures, one per offending path
+        errors: Vec<String>,
+    },
+}
+
+/// Lifecycle of a schema version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum VersionStatus {
+    /// Delivered to subscribers pinned to it
+    Current,
+    /// Still delivered, alongside the latest version, until the sunset date
+    Deprecated {
+        /// When subscribers are moved to the latest version
+        sunset_at: DateTime<Utc>,
+    },
+    /// No longer delivered
+    Retired,
+}
+
+/// One version of an event schema
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaVersion {
+    /// Version number
+    pub version: u32,
+    /// JSON Schema for the `data` object
+    pub schema: Value,
+    /// Lifecycle status
+    #[serde(flatten)]
+    pub status: VersionStatus,
+    /// When the version was registered
+    pub released_at: DateTime<Utc>,
+    #[serde(skip)]
+    downgrade: Option<Downgrade>,
+}
+
+/// Catalog entry for an event type
+#[derive(Debug, Clone, Serialize)]
+pub struct EventDefinition {
+    /// Event type name, e.g. `alert.created`
+    pub event_type: String,
+    /// Human-readable description
+    pub description: String,
+    /// Versions, oldest first
+    pub versions: BTreeMap<u32, SchemaVersion>,
+}
+
+impl EventDefinition {
+    /// Newest version that is not retired
+    pub fn latest(&self) -> Option<u32> {
+        self.versions.values()
+            .rev()
+            .find(|v| v.status != VersionStatus::Retired)
+            .map(|v| v.version)
+    }
+}
+
+/// Catalog summary served by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTypeSummary {
+    /// Event type name
+    pub event_type: String,
+    /// Human-readable description
+    pub description: String,
+    /// Latest deliverable version
+    pub latest_version: Option<u32>,
+    /// All versions and their status
+    pub versions: Vec<VersionSummary>,
+}
+
+/// Version summary
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionSummary {
+    /// Version number
+    pub version: u32,
+    /// Lifecycle status
+    #[serde(flatten)]
+    pub status: VersionStatus,
+}
+
+/// A payload rendered for one schema version
+#[derive(Debug, Clone)]
+pub struct RenderedPayload {
+    /// Schema version of `data`
+    pub version: u32,
+    /// Payload conforming to the version's schema
+    pub data: Value,
+    /// Set when the version is deprecated
+    pub sunset_at: Option<DateTime<Utc>>,
+}
+
+/// Registry of outbound event schemas
+pub struct SchemaRegistry {
+    events: RwLock<HashMap<String, EventDefinition>>,
+}
+
+impl SchemaRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self {
+            events: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Registry preloaded with the platform event catalog
+    pub fn with_builtin() -> Self {
+        let registry = Self::new();
+        builtin::register(&registry);
+        registry
+    }
+
+    /// Add an event type to the catalog
+    pub fn register_event(&self, event_type: &str, description: &str) {
+        self.events.write()
+            .entry(event_type.to_string())
+            .or_insert_with(|| EventDefinition {
+                event_type: event_type.to_string(),
+                description: description.to_string(),
+                versions: BTreeMap::new(),
+            });
+    }
+
+    /// Register a new schema version
+    ///
+    /// `downgrade` converts a payload of this version to the previous one and
+    /// is stored on the previous version; omit it when the change is additive.
+    pub fn add_version(
+        &self,
+        event_type: &str,
+        version: u32,
+        schema: Value,
+        downgrade: Option<Downgrade>,
+    ) -> Result<(), SchemaError> {
+        let mut events = self.events.write();
+        let def = events.get_mut(event_type)
+            .ok_or_else(|| SchemaError::UnknownEvent(event_type.to_string()))?;
+
+        if let Some((&newest, _)) = def.versions.iter().next_back() {
+            if version <= newest {
+                return Err(SchemaError::OutOfOrder(event_type.to_string(), version, newest));
+            }
+            if let Some(previous) = def.versions.get_mut(&newest) {
+                previous.downgrade = downgrade;
+            }
+        }
+
+        def.versions.insert(version, SchemaVersion {
+            version,
+            schema,
+            status: VersionStatus::Current,
+            released_at: Utc::now(),
+            downgrade: None,
+        });
+
+        Ok(())
+    }
+
+    /// Start the deprecation period for a current version
+    ///
+    /// A deprecated version keeps its original sunset date, and a retired one
+    /// can't be brought back for dual-emit.
+    pub fn deprecate(&self, event_type: &str, version: u32, sunset_at: DateTime<Utc>) -> Result<(), SchemaError> {
+        let mut events = self.events.write();
+        let def = events.get_mut(event_type)
+            .ok_or_else(|| SchemaError::UnknownEvent(event_type.to_string()))?;
+        let v = def.versions.get_mut(&version)
+            .ok_or_else(|| SchemaError::UnknownVersion(event_type.to_string(), version))?;
+
+        let current = match v.status {
+            VersionStatus::Current => None,
+            VersionStatus::Deprecated { .. } => Some("deprecated"),
+            VersionStatus::Retired => Some("retired"),
+        };
+        if let Some(status) = current {
+            return Err(SchemaError::NotCurrent(event_type.to_string(), version, status));
+        }
+        v.status = VersionStatus::Deprecated { sunset_at };
+        Ok(())
+    }
+
+    /// Stop delivering a version immediately
+    pub fn retire(&self, event_type: &str, version: u32) -> Result<(), SchemaError> {
+        self.set_status(event_type, version, VersionStatus::Retired)
+    }
+
+    fn set_status(&self, event_type: &str, version: u32, status: VersionStatus) -> Result<(), SchemaError> {
+        let mut events = self.events.write();
+        let def = events.get_mut(event_type)
+            .ok_or_else(|| SchemaError::UnknownEvent(event_type.to_string()))?;
+        let v = def.versions.get_mut(&version)
+            .ok_or_else(|| SchemaError::UnknownVersion(event_type.to_string(), version))?;
+        v.status = status;
+        Ok(())
+    }
+
+    /// Whether the event type is in the catalog
+    pub fn contains(&self, event_type: &str) -> bool {
+        self.events.read().contains_key(event_type)
+    }
+
+    /// Event definition with all versions
+    pub fn get(&self, event_type: &str) -> Option<EventDefinition> {
+        self.events.read().get(event_type).cloned()
+    }
+
+    /// One schema version
+    pub fn schema(&self, event_type: &str, version: u32) -> Option<SchemaVersion> {
+        self.events.read().get(event_type)?.versions.get(&version).cloned()
+    }
+
+    /// Catalog summary, sorted by event type
+    pub fn catalog(&self) -> Vec<EventTypeSummary> {
+        let events = self.events.read();
+        let mut catalog: Vec<_> = events.values().map(|def| EventTypeSummary {
+            event_type: def.event_type.clone(),
+            description: def.description.clone(),
+            latest_version: def.latest(),
+            versions: def.versions.values()
+                .map(|v| VersionSummary { version: v.version, status: v.status })
+                .collect(),
+        }).collect();
+        catalog.sort_by(|a, b| a.event_type.cmp(&b.event_type));
+        catalog
+    }
+
+    /// Check that a subscription may pin a version
+    pub fn check_pin(&self, event_type: &str, version: u32) -> Result<(), SchemaError> {
+        match self.schema(event_type, version) {
+            None if !self.contains(event_type) => Err(SchemaError::UnknownEvent(event_type.to_string())),
+            None => Err(SchemaError::UnknownVersion(event_type.to_string(), version)),
+            Some(v) if v.status == VersionStatus::Retired => Err(SchemaError::Retired(event_type.to_string(), version)),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Versions to deliver to a subscriber with an optional pin
+    ///
+    /// A pin on a deprecated version yields both the pinned and the latest
+    /// version until sunset; after that, or with no pin, only the latest.
+    pub fn versions_for(&self, event_type: &str, pinned: Option<u32>, now: DateTime<Utc>) -> Vec<u32> {
+        let events = self.events.read();
+        let Some(def) = events.get(event_type) else { return Vec::new() };
+        let Some(latest) = def.latest() else { return Vec::new() };
+
+        let Some(pinned) = pinned.filter(|p| *p != latest) else { return vec![latest] };
+
+        match def.versions.get(&pinned).map(|v| v.status) {
+            Some(VersionStatus::Current) => vec![pinned],
+            Some(VersionStatus::Deprecated { sunset_at }) if now < sunset_at => vec![pinned, latest],
+            _ => vec![latest],
+        }
+    }
+
+    /// Render a latest-version payload for a target version and validate it
+    pub fn render(&self, event_type: &str, data: &Value, version: u32) -> Result<RenderedPayload, SchemaError> {
+        let events = self.events.read();
+        let def = events.get(event_type)
+            .ok_or_else(|| SchemaError::UnknownEvent(event_type.to_string()))?;
+        let target = def.versions.get(&version)
+            .ok_or_else(|| SchemaError::UnknownVersion(event_type.to_string(), version))?;
+
+        // Walk down from the newest version applying each downgrade
+        let mut data = data.clone();
+        for v in def.versions.range(version..).rev().skip(1).map(|(_, v)| v) {
+            if let Some(downgrade) = v.downgrade {
+                data = downgrade(data);
+            }
+        }
+
+        let errors = validate(&target.schema, &data);
+        if !errors.is_empty() {
+            return Err(SchemaError::Invalid {
+                event: event_type.to_string(),
+                version,
+                errors,
+            });
+        }
+
+        Ok(RenderedPayload {
+            version,
+            data,
+            sunset_at: match target.status {
+                VersionStatus::Deprecated { sunset_at } => Some(sunset_at),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl Default for SchemaRegistry {
+    fn default() -> Self { Self::new() }
+}
+
+/// Validate a value against the JSON Schema subset used by the catalog
+///
+/// Supports `type`, `enum`, `required`, `properties`,
+/// `additionalProperties: false`, `items` and `minimum`. Returns one message
+/// per violation.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "$", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+            errors.push(format!("{}: expected {}", path, types.join(" or ")));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: not one of the allowed values", path));
+        }
+    }
+
+    if let (Some(min), Some(n)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+        if n < min {
+            errors.push(format!("{}: below minimum {}", path, min));
+        }
+    }
+
+    if let Some(obj) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for field in required.iter().filter_map(Value::as_str) {
+                if !obj.contains_key(field) {
+                    errors.push(format!("{}.{}: required", path, field));
+                }
+            }
+        }
+
+        for (key, v) in obj {
+            match properties.and_then(|p| p.get(key)) {
+                Some(sub) => validate_at(sub, v, &format!("{}.{}", path, key), errors),
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    errors.push(format!("{}.{}: not allowed", path, key));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Some(items), Some(arr)) = (schema.get("items"), value.as_array()) {
+        for (i, v) in arr.iter().enumerate() {
+            validate_at(items, v, &format!("{}[{}]", path, i), errors);
+        }
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Platform event catalog
+mod builtin {
+    use super::*;
+
+    pub(super) fn register(registry: &SchemaRegistry) {
+        registry.register_event("alert.created", "A security alert was raised");
+        registry.add_version("alert.created", 1, json!({
+            "type": "object",
+            "required": ["alert_id", "severity", "title", "created_at"],
+            "properties": {
+                "alert_id": { "type": "string" },
+                "severity": { "enum": ["low", "medium", "high", "critical"] },
+                "title": { "type": "string" },
+                "description": { "type": "string" },
+                "created_at": { "type": "string", "format": "date-time" }
+            },
+            "additionalProperties": false
+        }), None).expect("builtin catalog");
+        registry.add_version("alert.created", 2, json!({
+            "type": "object",
+            "required": ["alert_id", "severity", "category", "title", "created_at"],
+            "properties": {
+                "alert_id": { "type": "string" },
+                "severity": { "enum": ["low", "medium", "high", "critical"] },
+                "category": { "type": "string" },
+                "title": { "type": "string" },
+                "description": { "type": "string" },
+                "source": {
+                    "type": "object",
+                    "properties": {
+                        "ip": { "type": ["string", "null"] },
+                        "user_id": { "type": ["string", "null"] }
+                    }
+                },
+                "mitre_techniques": { "type": "array", "items": { "type": "string" } },
+                "created_at": { "type": "string", "format": "date-time" }
+            },
+            "additionalProperties": false
+        }), Some(alert_created_v2_to_v1)).expect("builtin catalog");
+
+        registry.register_event("invoice.finalized", "An invoice was finalized and is ready for payment");
+        registry.add_version("invoice.finalized", 1, json!({
+            "type": "object",
+            "required": ["invoice_id", "tenant_id", "amount_cents", "currency", "finalized_at"],
+            "properties": {
+                "invoice_id": { "type": "string" },
+                "tenant_id": { "type": "string" },
+                "amount_cents": { "type": "integer", "minimum": 0 },
+                "currency": { "type": "string" },
+                "due_at": { "type": "string", "format": "date-time" },
+                "finalized_at": { "type": "string", "format": "date-time" }
+            }
+        }), None).expect("builtin catalog");
+
+        registry.register_event("tunnel.down", "A site tunnel to a PoP went down");
+        registry.add_version("tunnel.down", 1, json!({
+            "type": "object",
+            "required": ["tunnel_id", "site_id", "pop_id", "down_since"],
+            "properties": {
+                "tunnel_id": { "type": "string" },
+                "site_id": { "type": "string" },
+                "pop_id": { "type": "string" },
+                "reason": { "type": "string" },
+                "down_since": { "type": "string", "format": "date-time" }
+            }
+        }), None).expect("builtin catalog");
+    }
+
+    /// v2 added `category`, `source` and `mitre_techniques`
+    fn alert_created_v2_to_v1(mut data: Value) -> Value {
+        if let Some(obj) = data.as_object_mut() {
+            obj.remove("category");
+            obj.remove("source");
+            obj.remove("mitre_techniques");
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn alert_v2() -> Value {
+        json!({
+            "alert_id": "a-1",
+            "severity": "high",
+            "category": "malware",
+            "title": "Beacon to known C2",
+            "source": { "ip": "10.0.0.8", "user_id": null },
+            "mitre_techniques": ["T1071"],
+            "created_at": "2026-10-18T09:00:00Z"
+        })
+    }
+
+    #[test]
+    fn test_render_latest_and_downgraded() {
+        let registry = SchemaRegistry::with_builtin();
+
+        let latest = registry.render("alert.created", &alert_v2(), 2).unwrap();
+        assert_eq!(latest.data, alert_v2());
+        assert_eq!(latest.sunset_at, None);
+
+        // v1 has additionalProperties: false, so the downgrade must strip the v2 fields
+        let v1 = registry.render("alert.created", &alert_v2(), 1).unwrap();
+        assert_eq!(v1.version, 1);
+        assert_eq!(v1.data, json!({
+            "alert_id": "a-1",
+            "severity": "high",
+            "title": "Beacon to known C2",
+            "created_at": "2026-10-18T09:00:00Z"
+        }));
+    }
+
+    #[test]
+    fn test_render_rejects_incompatible_payload() {
+        let registry = SchemaRegistry::with_builtin();
+        let mut data = alert_v2();
+        data["severity"] = json!("urgent");
+        data.as_object_mut().unwrap().remove("category");
+
+        match registry.render("alert.created", &data, 2) {
+            Err(SchemaError::Invalid { version, errors, .. }) => {
+                assert_eq!(version, 2);
+                assert_eq!(errors.len(), 2);
+            }
+            other => panic!("expected a validation error, got {:?}", other.map(|p| p.data)),
+        }
+        assert!(matches!(registry.render("alert.created", &alert_v2(), 3), Err(SchemaError::UnknownVersion(_, 3))));
+        assert!(matches!(registry.render("alert.deleted", &alert_v2(), 1), Err(SchemaError::UnknownEvent(_))));
+    }
+
+    #[test]
+    fn test_validate_schema_subset() {
+        let schema = json!({
+            "type": "object",
+            "required": ["amount"],
+            "properties": {
+                "amount": { "type": "integer", "minimum": 0 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "note": { "type": ["string", "null"] }
+            },
+            "additionalProperties": false
+        });
+        assert!(validate(&schema, &json!({ "amount": 5, "tags": ["a"], "note": null })).is_empty());
+
+        let errors = validate(&schema, &json!({ "amount": -1, "tags": ["a", 2], "extra": true }));
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_eq!(validate(&schema, &json!({})).len(), 1);
+        assert_eq!(validate(&schema, &json!("amount")).len(), 1);
+    }
+
+    #[test]
+    fn test_versions_registered_in_order() {
+        let registry = SchemaRegistry::new();
+        assert!(matches!(
+            registry.add_version("tunnel.up", 1, json!({}), None),
+            Err(SchemaError::UnknownEvent(_))
+        ));
+
+        registry.register_event("tunnel.up", "A tunnel came up");
+        registry.add_version("tunnel.up", 1, json!({}), None).unwrap();
+        registry.add_version("tunnel.up", 3, json!({}), None).unwrap();
+        assert!(matches!(
+            registry.add_version("tunnel.up", 2, json!({}), None),
+            Err(SchemaError::OutOfOrder(_, 2, 3))
+        ));
+        assert_eq!(registry.get("tunnel.up").unwrap().latest(), Some(3));
+    }
+
+    #[test]
+    fn test_deprecation_lifecycle() {
+        let registry = SchemaRegistry::with_builtin();
+        let now = Utc::now();
+        let sunset = now + Duration::days(90);
+
+        // Unpinned and latest-pinned subscribers get the latest only
+        assert_eq!(registry.versions_for("alert.created", None, now), vec![2]);
+        assert_eq!(registry.versions_for("alert.created", Some(2), now), vec![2]);
+        assert_eq!(registry.versions_for("alert.created", Some(1), now), vec![1]);
+
+        registry.deprecate("alert.created", 1, sunset).unwrap();
+        assert_eq!(registry.versions_for("alert.created", Some(1), now), vec![1, 2]);
+        assert_eq!(registry.versions_for("alert.created", Some(1), sunset), vec![2]);
+        let rendered = registry.render("alert.created", &alert_v2(), 1).unwrap();
+        assert_eq!(rendered.sunset_at, Some(sunset));
+
+        // The sunset date can't be moved by deprecating again
+        assert!(matches!(
+            registry.deprecate("alert.created", 1, now + Duration::days(1)),
+            Err(SchemaError::NotCurrent(_, 1, "deprecated"))
+        ));
+        assert_eq!(registry.render("alert.created", &alert_v2(), 1).unwrap().sunset_at, Some(sunset));
+
+        registry.retire("alert.created", 1).unwrap();
+        assert_eq!(registry.versions_for("alert.created", Some(1), now), vec![2]);
+        assert!(matches!(registry.check_pin("alert.created", 1), Err(SchemaError::Retired(_, 1))));
+        assert!(matches!(
+            registry.deprecate("alert.created", 1, sunset),
+            Err(SchemaError::NotCurrent(_, 1, "retired"))
+        ));
+        assert!(matches!(registry.deprecate("alert.created", 9, sunset), Err(SchemaError::UnknownVersion(_, 9))));
+    }
+
+    #[test]
+    fn test_retiring_latest_falls_back() {
+        let registry = SchemaRegistry::with_builtin();
+        registry.retire("alert.created", 2).unwrap();
+        assert_eq!(registry.get("alert.created").unwrap().latest(), Some(1));
+        assert_eq!(registry.versions_for("alert.created", None, Utc::now()), vec![1]);
+
+        let catalog = registry.catalog();
+        let names: Vec<&str> = catalog.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(names, vec!["alert.created", "invoice.finalized", "tunnel.down"]);
+        assert_eq!(catalog[0].latest_version, Some(1));
+        assert!(matches!(registry.check_pin("alert.deleted", 1), Err(SchemaError::UnknownEvent(_))));
+        assert!(registry.check_pin("alert.created", 1).is_ok());
+    }
+}
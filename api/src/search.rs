@@ -0,0 +1,696 @@
+//! Management-Plane Search
+//!
+//! One tantivy index holds every searchable entity (policies, sites, users,
+//! tunnels, alerts, tickets) for every tenant. Queries are always scoped to
+//! a tenant and to the entity kinds the caller may read. Free text matches
+//! names and descriptions; IP addresses and CIDRs mentioned anywhere in an
+//! entity are also indexed verbatim so `10.4.0.0/16` finds the policy that
+//! mentions it. Entity changes are applied incrementally by key.
+
+use crate::middleware::permissions::{has_permission, Permission};
+use crate::models::{Policy, Site, Ticket, Tunnel, User};
+use crate::routes::alerts::Alert;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Instant;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::{AllQuery, BooleanQuery, BoostQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+use thiserror::Error;
+
+/// Queries slower than this are logged
+const SLOW_QUERY_MS: u128 = 200;
+
+/// Deepest page a caller may ask for; tantivy keeps `offset + limit` hits
+/// in memory while collecting
+pub const MAX_OFFSET: usize = 1_000;
+
+/// Writer heap budget
+const WRITER_HEAP_BYTES: usize = 50_000_000;
+
+/// Search errors
+#[derive(Debug, Error)]
+pub enum SearchError {
+    /// Index could not be opened, written or read
+    #[error("search index error: {0}")]
+    Index(#[from] tantivy::TantivyError),
+    /// Index directory could not be opened
+    #[error("search index directory error: {0}")]
+    Directory(String),
+}
+
+/// Searchable entity kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    /// Access policy
+    Policy,
+    /// Site / edge location
+    Site,
+    /// Tenant user
+    User,
+    /// Tunnel
+    Tunnel,
+    /// Security alert
+    Alert,
+    /// Support ticket
+    Ticket,
+}
+
+impl EntityKind {
+    /// Every kind, in display order
+    pub const ALL: [EntityKind; 6] = [
+        EntityKind::Policy,
+        EntityKind::Site,
+        EntityKind::User,
+        EntityKind::Tunnel,
+        EntityKind::Alert,
+        EntityKind::Ticket,
+    ];
+
+    /// Stable identifier used in the index and query strings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Policy => "policy",
+            EntityKind::Site => "site",
+            EntityKind::User => "user",
+            EntityKind::Tunnel => "tunnel",
+            EntityKind::Alert => "alert",
+            EntityKind::Ticket => "ticket",
+        }
+    }
+
+    /// Parse a kind name, singular or plural
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|k| s == k.as_str() || s.strip_suffix('s') == Some(k.as_str()))
+    }
+
+    /// Permission needed to see entities of this kind
+    pub fn read_permission(&self) -> Permission {
+        match self {
+            EntityKind::Policy => Permission::PoliciesRead,
+            EntityKind::Site => Permission::SitesRead,
+            EntityKind::User => Permission::UsersRead,
+            EntityKind::Tunnel => Permission::TunnelsRead,
+            EntityKind::Alert => Permission::AlertsRead,
+            EntityKind::Ticket => Permission::TicketsRead,
+        }
+    }
+
+    /// Kinds readable with the given permissions
+    pub fn readable(permissions: &HashSet<Permission>) -> Vec<EntityKind> {
+        Self::ALL.into_iter().filter(|k| has_permission(permissions, k.read_permission())).collect()
+    }
+}
+
+/// Entity as indexed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchDocument {
+    /// Owning tenant
+    pub tenant_id: String,
+    /// Entity kind
+    pub kind: EntityKind,
+    /// Entity ID
+    pub id: String,
+    /// Name or title
+    pub title: String,
+    /// Free text: description, conditions, addresses
+    pub body: String,
+    /// Typed filter values, e.g. `status` = `open`
+    pub filters: BTreeMap<String, String>,
+}
+
+impl SearchDocument {
+    /// Document with only a title
+    pub fn new(tenant_id: impl Into<String>, kind: EntityKind, id: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            kind,
+            id: id.into(),
+            title: title.into(),
+            body: String::new(),
+            filters: BTreeMap::new(),
+        }
+    }
+
+    /// Append free text
+    pub fn with_body(mut self, text: impl AsRef<str>) -> Self {
+        let text = text.as_ref().trim();
+        if !text.is_empty() {
+            if !self.body.is_empty() {
+                self.body.push('\n');
+            }
+            self.body.push_str(text);
+        }
+        self
+    }
+
+    /// Add a typed filter value
+    pub fn with_filter(mut self, key: &str, value: impl ToString) -> Self {
+        self.filters.insert(key.to_ascii_lowercase(), value.to_string().to_ascii_lowercase());
+        self
+    }
+
+    /// Index an access policy
+    pub fn policy(tenant_id: &str, policy: &Policy) -> Self {
+        let conditions: Vec<String> = policy.conditions.iter()
+            .map(|c| format!("{} {} {}", c.field, c.operator, c.value))
+            .collect();
+        Self::new(tenant_id, EntityKind::Policy, policy.id.to_string(), &policy.name)
+            .with_body(&policy.description)
+            .with_body(conditions.join("\n"))
+            .with_filter("action", format!("{:?}", policy.action))
+            .with_filter("enabled", policy.enabled)
+    }
+
+    /// Index a site
+    pub fn site(tenant_id: &str, site: &Site) -> Self {
+        Self::new(tenant_id, EntityKind::Site, site.id.to_string(), &site.name)
+            .with_body(&site.location)
+            .with_filter("status", format!("{:?}", site.status))
+    }
+
+    /// Index a user
+    pub fn user(tenant_id: &str, user: &User) -> Self {
+        Self::new(tenant_id, EntityKind::User, user.id.to_string(), &user.name)
+            .with_body(&user.email)
+            .with_filter("role", format!("{:?}", user.role))
+            .with_filter("status", &user.status)
+            .with_filter("mfa", user.mfa_enabled)
+    }
+
+    /// Index a tunnel
+    pub fn tunnel(tenant_id: &str, tunnel: &Tunnel) -> Self {
+        Self::new(tenant_id, EntityKind::Tunnel, tunnel.id.to_string(), &tunnel.name)
+            .with_body(format!("{} {} {}", tunnel.tunnel_type, tunnel.local_ip, tunnel.remote_ip))
+            .with_filter("type", &tunnel.tunnel_type)
+            .with_filter("status", &tunnel.status)
+    }
+
+    /// Index a security alert
+    pub fn alert(tenant_id: &str, alert: &Alert) -> Self {
+        let mut doc = Self::new(tenant_id, EntityKind::Alert, alert.id.to_string(), &alert.title)
+            .with_body(&alert.description)
+            .with_filter("severity", &alert.severity)
+            .with_filter("status", &alert.status)
+            .with_filter("category", &alert.category);
+        if let Some(ip) = &alert.source_ip {
+            doc = doc.with_body(ip);
+        }
+        if let Some(user) = &alert.user_id {
+            doc = doc.with_body(user).with_filter("user", user);
+        }
+        doc
+    }
+
+    /// Index a support ticket
+    pub fn ticket(tenant_id: &str, ticket: &Ticket) -> Self {
+        Self::new(tenant_id, EntityKind::Ticket, ticket.id.to_string(), &ticket.subject)
+            .with_body(&ticket.description)
+            .with_body(&ticket.requester)
+            .with_filter("status", &ticket.status)
+            .with_filter("priority", &ticket.priority)
+    }
+
+    fn key(&self) -> String {
+        document_key(&self.tenant_id, self.kind, &self.id)
+    }
+}
+
+/// Change to a searchable entity
+#[derive(Debug, Clone)]
+pub enum EntityChange {
+    /// Entity created or updated
+    Upserted(SearchDocument),
+    /// Entity deleted
+    Deleted {
+        /// Owning tenant
+        tenant_id: String,
+        /// Entity kind
+        kind: EntityKind,
+        /// Entity ID
+        id: String,
+    },
+}
+
+/// Search request
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Free text; empty matches everything in scope
+    pub text: Option<String>,
+    /// Restrict to these kinds (empty means all readable kinds)
+    pub kinds: Vec<EntityKind>,
+    /// Typed filters, all of which must match
+    pub filters: BTreeMap<String, String>,
+    /// Page size
+    pub limit: usize,
+    /// Hits to skip, at most [`MAX_OFFSET`]
+    pub offset: usize,
+}
+
+/// One search hit
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// Entity kind
+    pub kind: EntityKind,
+    /// Entity ID
+    pub id: String,
+    /// Name or title
+    pub title: String,
+    /// Highlighted body excerpt, when free text was given
+    pub snippet: Option<String>,
+    /// Relevance score
+    pub score: f32,
+}
+
+/// Search results page
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    /// Hits on this page
+    pub hits: Vec<SearchHit>,
+    /// Total matching entities
+    pub total: usize,
+    /// Query time in milliseconds
+    pub took_ms: u64,
+}
+
+struct Fields {
+    key: Field,
+    tenant: Field,
+    kind: Field,
+    id: Field,
+    title: Field,
+    body: Field,
+    filters: Field,
+    addresses: Field,
+}
+
+/// Cross-entity search index
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    fields: Fields,
+}
+
+impl SearchIndex {
+    /// Index held in memory; rebuilt from entity stores on start
+    pub fn in_memory() -> Result<Self, SearchError> {
+        let (schema, fields) = build_schema();
+        Self::with_index(Index::create_in_ram(schema), fields)
+    }
+
+    /// Index persisted under `dir`, created if missing
+    pub fn open(dir: &Path) -> Result<Self, SearchError> {
+        std::fs::create_dir_all(dir).map_err(|e| SearchError::Directory(e.to_string()))?;
+        let directory = tantivy::directory::MmapDirectory::open(dir)
+            .map_err(|e| SearchError::Directory(e.to_string()))?;
+        let (schema, fields) = build_schema();
+        Self::with_index(Index::open_or_create(directory, schema)?, fields)
+    }
+
+    fn with_index(index: Index, fields: Fields) -> Result<Self, SearchError> {
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
+        let writer = index.writer(WRITER_HEAP_BYTES)?;
+        Ok(Self { index, reader, writer: Mutex::new(writer), fields })
+    }
+
+    /// Apply one entity change and make it searchable
+    pub fn apply(&self, change: EntityChange) -> Result<(), SearchError> {
+        self.apply_batch(std::iter::once(change))
+    }
+
+    /// Apply several changes in one commit
+    pub fn apply_batch(&self, changes: impl IntoIterator<Item = EntityChange>) -> Result<(), SearchError> {
+        let mut writer = self.writer.lock();
+        for change in changes {
+            match change {
+                EntityChange::Upserted(doc) => {
+                    writer.delete_term(Term::from_field_text(self.fields.key, &doc.key()));
+                    writer.add_document(self.to_tantivy(&doc))?;
+                }
+                EntityChange::Deleted { tenant_id, kind, id } => {
+                    writer.delete_term(Term::from_field_text(self.fields.key, &document_key(&tenant_id, kind, &id)));
+                }
+            }
+        }
+        writer.commit()?;
+        drop(writer);
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Apply a change from a write path; indexing failures are logged so
+    /// they never fail the write itself
+    pub fn record(&self, change: EntityChange) {
+        if let Err(e) = self.apply(change) {
+            tracing::warn!("Search index update failed: {}", e);
+        }
+    }
+
+    /// Index or reindex one entity
+    pub fn upsert(&self, doc: SearchDocument) -> Result<(), SearchError> {
+        self.apply(EntityChange::Upserted(doc))
+    }
+
+    /// Remove one entity
+    pub fn remove(&self, tenant_id: &str, kind: EntityKind, id: &str) -> Result<(), SearchError> {
+        self.apply(EntityChange::Deleted { tenant_id: tenant_id.to_string(), kind, id: id.to_string() })
+    }
+
+    /// Search one tenant's entities, trimmed to the kinds the caller may read
+    pub fn search(
+        &self,
+        tenant_id: &str,
+        permissions: &HashSet<Permission>,
+        query: &SearchQuery,
+    ) -> Result<SearchResults, SearchError> {
+        let started = Instant::now();
+        let readable = EntityKind::readable(permissions);
+        let kinds: Vec<EntityKind> = if query.kinds.is_empty() {
+            readable
+        } else {
+            query.kinds.iter().copied().filter(|k| readable.contains(k)).collect()
+        };
+        if kinds.is_empty() {
+            return Ok(SearchResults { hits: Vec::new(), total: 0, took_ms: 0 });
+        }
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![
+            (Occur::Must, self.term(self.fields.tenant, tenant_id)),
+            (Occur::Must, Box::new(BooleanQuery::new(
+                kinds.iter().map(|k| (Occur::Should, self.term(self.fields.kind, k.as_str()))).collect(),
+            ))),
+        ];
+        for (key, value) in &query.filters {
+            let value = format!("{}={}", key.to_ascii_lowercase(), value.to_ascii_lowercase());
+            clauses.push((Occur::Must, self.term(self.fields.filters, &value)));
+        }
+
+        let text = query.text.as_deref().map(str::trim).filter(|t| !t.is_empty());
+        let text_query = text.map(|t| self.text_query(t));
+        match &text_query {
+            Some(q) => clauses.push((Occur::Must, q.box_clone())),
+            None => clauses.push((Occur::Must, Box::new(AllQuery))),
+        }
+        let combined = BooleanQuery::new(clauses);
+
+        let searcher = self.reader.searcher();
+        let limit = query.limit.clamp(1, 100);
+        let offset = query.offset.min(MAX_OFFSET);
+        let (top, total) = searcher.search(&combined, &(TopDocs::with_limit(limit).and_offset(offset), Count))?;
+
+        let snippets = match &text_query {
+            Some(q) => Some(SnippetGenerator::create(&searcher, &**q, self.fields.body)?),
+            None => None,
+        };
+
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let text = |field: Field| doc.get_first(field).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            let Some(kind) = EntityKind::parse(&text(self.fields.kind)) else { continue };
+            let snippet = snippets.as_ref()
+                .map(|g| g.snippet_from_doc(&doc).to_html())
+                .filter(|s| !s.is_empty());
+            hits.push(SearchHit { kind, id: text(self.fields.id), title: text(self.fields.title), snippet, score });
+        }
+
+        let took = started.elapsed().as_millis();
+        if took > SLOW_QUERY_MS {
+            tracing::warn!(tenant_id, took_ms = took as u64, "Slow management-plane search");
+        }
+
+        Ok(SearchResults { hits, total, took_ms: took as u64 })
+    }
+
+    /// Free text over title and body, plus exact address matches
+    fn text_query(&self, text: &str) -> Box<dyn Query> {
+        let mut parser = QueryParser::for_index(&self.index, vec![self.fields.title, self.fields.body]);
+        parser.set_field_boost(self.fields.title, 2.0);
+        parser.set_conjunction_by_default();
+        // User input is not query syntax; lenient parsing drops what it can't use
+        let (parsed, _) = parser.parse_query_lenient(text);
+
+        let addresses = extract_addresses(text);
+        if addresses.is_empty() {
+            return parsed;
+        }
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Should, parsed)];
+        for address in addresses {
+            clauses.push((Occur::Should, Box::new(BoostQuery::new(self.term(self.fields.addresses, &address), 3.0))));
+        }
+        Box::new(BooleanQuery::new(clauses))
+    }
+
+    fn term(&self, field: Field, value: &str) -> Box<dyn Query> {
+        Box::new(TermQuery::new(Term::from_field_text(field, value), IndexRecordOption::Basic))
+    }
+
+    fn to_tantivy(&self, doc: &SearchDocument) -> TantivyDocument {
+        let f = &self.fields;
+        let mut out = TantivyDocument::default();
+        out.add_text(f.key, doc.key());
+        out.add_text(f.tenant, &doc.tenant_id);
+        out.add_text(f.kind, doc.kind.as_str());
+        out.add_text(f.id, &doc.id);
+        out.add_text(f.title, &doc.title);
+        out.add_text(f.body, &doc.body);
+        for (key, value) in &doc.filters {
+            out.add_text(f.filters, format!("{}={}", key, value));
+        }
+        for address in extract_addresses(&format!("{} {}", doc.title, doc.body)) {
+            out.add_text(f.addresses, address);
+        }
+        out
+    }
+}
+
+fn build_schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        key: builder.add_text_field("key", STRING),
+        tenant: builder.add_text_field("tenant", STRING),
+        kind: builder.add_text_field("kind", STRING | STORED),
+        id: builder.add_text_field("id", STRING | STORED),
+        title: builder.add_text_field("title", TEXT | STORED),
+        body: builder.add_text_field("body", TEXT | STORED),
+        filters: builder.add_text_field("filters", STRING),
+        addresses: builder.add_text_field("addresses", STRING),
+    };
+    (builder.build(), fields)
+}
+
+fn document_key(tenant_id: &str, kind: EntityKind, id: &str) -> String {
+    format!("{}/{}/{}", tenant_id, kind.as_str(), id)
+}
+
+/// IP addresses and CIDRs in text, normalised
+fn extract_addresses(text: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '(' | ')' | '[' | ']' | '"' | '\'')) {
+        let token = token.trim_end_matches('.');
+        let (addr, prefix) = match token.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (token, None),
+        };
+        let Ok(ip) = addr.parse::<IpAddr>() else { continue };
+        let max = if ip.is_ipv4() { 32 } else { 128 };
+        let normalised = match prefix.map(|p| p.parse::<u8>()) {
+            None => ip.to_string(),
+            Some(Ok(len)) if len <= max => format!("{}/{}", ip, len),
+            Some(_) => continue,
+        };
+        if !found.contains(&normalised) {
+            found.push(normalised);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PolicyAction, PolicyCondition};
+
+    fn policy(name: &str, description: &str) -> Policy {
+        Policy {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            description: description.to_string(),
+            enabled: true,
+            priority: 100,
+            conditions: vec![PolicyCondition {
+                field: "destination".to_string(),
+                operator: "in".to_string(),
+                value: "10.4.0.0/16".to_string(),
+            }],
+            action: PolicyAction::Block,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn tunnel(name: &str) -> Tunnel {
+        Tunnel {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            tunnel_type: "wireguard".to_string(),
+            status: "up".to_string(),
+            local_ip: "10.0.0.1".to_string(),
+            remote_ip: "45.67.89.10".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn ticket(subject: &str) -> Ticket {
+        Ticket {
+            id: uuid::Uuid::new_v4(),
+            subject: subject.to_string(),
+            description: "Drops every few minutes since the ISP change".to_string(),
+            status: "open".to_string(),
+            priority: "high".to_string(),
+            requester: "netops@example.com".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn text(text: &str) -> SearchQuery {
+        SearchQuery { text: Some(text.to_string()), limit: 20, ..SearchQuery::default() }
+    }
+
+    fn admin() -> HashSet<Permission> {
+        Permission::for_role("admin")
+    }
+
+    fn ids(results: &SearchResults) -> Vec<String> {
+        results.hits.iter().map(|h| h.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_results_limited_to_tenant() {
+        let index = SearchIndex::in_memory().unwrap();
+        let ours = policy("Block finance subnet", "Finance lockdown");
+        let theirs = policy("Block finance subnet", "Finance lockdown");
+        index.upsert(SearchDocument::policy("tenant-a", &ours)).unwrap();
+        index.upsert(SearchDocument::policy("tenant-b", &theirs)).unwrap();
+
+        let results = index.search("tenant-a", &admin(), &text("finance")).unwrap();
+        assert_eq!(results.total, 1);
+        assert_eq!(ids(&results), vec![ours.id.to_string()]);
+
+        let results = index.search("tenant-c", &admin(), &text("finance")).unwrap();
+        assert_eq!(results.total, 0);
+    }
+
+    #[test]
+    fn test_kinds_trimmed_to_permissions() {
+        let index = SearchIndex::in_memory().unwrap();
+        let tunnel = tunnel("HQ flapping link");
+        let ticket = ticket("Tunnel flapping");
+        index.upsert(SearchDocument::tunnel("tenant-a", &tunnel)).unwrap();
+        index.upsert(SearchDocument::ticket("tenant-a", &ticket)).unwrap();
+
+        let results = index.search("tenant-a", &admin(), &text("flapping")).unwrap();
+        assert_eq!(results.total, 2);
+
+        // Viewers may read tunnels but not tickets
+        let viewer = Permission::for_role("viewer");
+        let results = index.search("tenant-a", &viewer, &text("flapping")).unwrap();
+        assert_eq!(ids(&results), vec![tunnel.id.to_string()]);
+
+        let only_tickets = SearchQuery { kinds: vec![EntityKind::Ticket], ..text("flapping") };
+        let results = index.search("tenant-a", &viewer, &only_tickets).unwrap();
+        assert_eq!(results.total, 0);
+
+        let results = index.search("tenant-a", &HashSet::new(), &text("flapping")).unwrap();
+        assert!(results.hits.is_empty());
+    }
+
+    #[test]
+    fn test_address_and_filter_matches() {
+        let index = SearchIndex::in_memory().unwrap();
+        let blocked = policy("Block lab", "Quarantine");
+        let mut allowed = policy("Allow lab", "Quarantine");
+        allowed.action = PolicyAction::Allow;
+        index.upsert(SearchDocument::policy("tenant-a", &blocked)).unwrap();
+        index.upsert(SearchDocument::policy("tenant-a", &allowed)).unwrap();
+
+        let results = index.search("tenant-a", &admin(), &text("10.4.0.0/16")).unwrap();
+        assert_eq!(results.total, 2);
+
+        let mut query = text("10.4.0.0/16");
+        query.filters.insert("action".to_string(), "Block".to_string());
+        let results = index.search("tenant-a", &admin(), &query).unwrap();
+        assert_eq!(ids(&results), vec![blocked.id.to_string()]);
+    }
+
+    #[test]
+    fn test_ticket_found_by_requester_and_status() {
+        let index = SearchIndex::in_memory().unwrap();
+        let ticket = ticket("Tunnel flapping");
+        index.upsert(SearchDocument::ticket("tenant-a", &ticket)).unwrap();
+
+        let mut query = text("netops");
+        query.filters.insert("status".to_string(), "open".to_string());
+        let results = index.search("tenant-a", &admin(), &query).unwrap();
+        assert_eq!(ids(&results), vec![ticket.id.to_string()]);
+        assert_eq!(results.hits[0].kind, EntityKind::Ticket);
+    }
+
+    #[test]
+    fn test_upsert_replaces_and_delete_removes() {
+        let index = SearchIndex::in_memory().unwrap();
+        let mut tunnel = tunnel("Old name");
+        index.upsert(SearchDocument::tunnel("tenant-a", &tunnel)).unwrap();
+        tunnel.name = "Renamed link".to_string();
+        index.upsert(SearchDocument::tunnel("tenant-a", &tunnel)).unwrap();
+
+        let all = SearchQuery { limit: 20, ..SearchQuery::default() };
+        let results = index.search("tenant-a", &admin(), &all).unwrap();
+        assert_eq!(results.total, 1);
+        assert_eq!(results.hits[0].title, "Renamed link");
+
+        index.remove("tenant-a", EntityKind::Tunnel, &tunnel.id.to_string()).unwrap();
+        assert_eq!(index.search("tenant-a", &admin(), &all).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_offset_clamped() {
+        let index = SearchIndex::in_memory().unwrap();
+        let changes = (0..5).map(|i| EntityChange::Upserted(SearchDocument::tunnel("tenant-a", &tunnel(&format!("link {}", i)))));
+        index.apply_batch(changes).unwrap();
+
+        let page = SearchQuery { limit: 2, offset: 4, ..SearchQuery::default() };
+        let results = index.search("tenant-a", &admin(), &page).unwrap();
+        assert_eq!((results.hits.len(), results.total), (1, 5));
+
+        let huge = SearchQuery { limit: 2, offset: usize::MAX, ..SearchQuery::default() };
+        let results = index.search("tenant-a", &admin(), &huge).unwrap();
+        assert_eq!((results.hits.len(), results.total), (0, 5));
+    }
+
+    #[test]
+    fn test_extract_addresses() {
+        assert_eq!(
+            extract_addresses("deny 10.4.0.0/16, allow (192.168.1.5). bad 10.0.0.0/40 and 10.4.0.0/16"),
+            vec!["10.4.0.0/16".to_string(), "192.168.1.5".to_string()]
+        );
+        assert_eq!(extract_addresses("route 2001:db8::/32"), vec!["2001:db8::/32".to_string()]);
+        assert!(extract_addresses("no addresses here").is_empty());
+    }
+
+    #[test]
+    fn test_kind_parse() {
+        assert_eq!(EntityKind::parse("Tickets"), Some(EntityKind::Ticket));
+        assert_eq!(EntityKind::parse(" policy "), Some(EntityKind::Policy));
+        assert_eq!(EntityKind::parse("widgets"), None);
+    }
+}
@@ -1,117 +1,419 @@
 //! Webhook Delivery System
+//!
+//! Internal components emit [`Event`]s through an [`EventPublisher`]; the
+//! delivery worker fans each event out to matching subscriptions. Every
+//! (subscription, payload) pair becomes a [`DeliveryRecord`] kept in a
+//! [`DeliveryStore`], so pending retries survive a restart and failed
+//! deliveries can be inspected and replayed.
+//!
+//! Requests carry an HMAC-SHA256 signature of `"{timestamp}.{body}"` keyed
+//! with the subscription secret, in the format the SDKs verify.
 
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use parking_lot::RwLock;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tokio::sync::mpsc;
 use uuid::Uuid;
+use crate::schema_registry::{SchemaError, SchemaRegistry};
+
+/// Signature header, `v1=<hex HMAC-SHA256>`
+pub const SIGNATURE_HEADER: &str = "X-OpenSASE-Signature";
+
+/// Unix timestamp covered by the signature
+pub const TIMESTAMP_HEADER: &str = "X-OpenSASE-Timestamp";
+
+/// Delivery id; stable across retries so receivers can deduplicate
+pub const DELIVERY_HEADER: &str = "X-OpenSASE-Delivery";
+
+/// Per-request timeout
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Succeeded deliveries are kept this long for inspection
+const SUCCEEDED_RETENTION_DAYS: i64 = 7;
+
+/// Failed deliveries are kept this long for replay
+const FAILED_RETENTION_DAYS: i64 = 30;
+
+/// Events buffered between publishers and the delivery worker
+const PUBLISH_BUFFER: usize = 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Webhook delivery errors
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    /// Payload rendering or schema pin failed
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+    /// Delivery store could not be read or written
+    #[error("webhook delivery store error: {0}")]
+    Store(String),
+    /// No subscription with this id
+    #[error("unknown webhook subscription: {0}")]
+    UnknownSubscription(Uuid),
+    /// No delivery with this id
+    #[error("unknown webhook delivery: {0}")]
+    UnknownDelivery(Uuid),
+    /// Delivery is still being retried
+    #[error("webhook delivery {0} is still pending")]
+    StillPending(Uuid),
+}
 
 /// Webhook manager
 pub struct WebhookDelivery {
     subscriptions: Arc<RwLock<HashMap<Uuid, WebhookConfig>>>,
-    queue: Arc<RwLock<Vec<WebhookEvent>>>,
-    dead_letter: Arc<RwLock<Vec<DeadLetter>>>,
+    deliveries: Arc<RwLock<HashMap<Uuid, DeliveryRecord>>>,
+    store: Arc<dyn DeliveryStore>,
+    schemas: Arc<SchemaRegistry>,
+    client: reqwest::Client,
 }
 
 impl WebhookDelivery {
+    /// Create with the built-in event catalog and an in-memory store
     pub fn new() -> Self {
+        Self::with_registry(Arc::new(SchemaRegistry::with_builtin()))
+    }
+
+    /// Create with a shared schema registry
+    pub fn with_registry(schemas: Arc<SchemaRegistry>) -> Self {
         Self {
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
-            queue: Arc::new(RwLock::new(Vec::new())),
-            dead_letter: Arc::new(RwLock::new(Vec::new())),
+            deliveries: Arc::new(RwLock::new(HashMap::new())),
+            store: Arc::new(MemoryDeliveryStore),
+            schemas,
+            client: reqwest::Client::new(),
         }
     }
 
-    /// Subscribe to events
-    pub fn subscribe(&self, config: WebhookConfig) -> Uuid {
+    /// Persist deliveries in `store`, resuming any it already holds
+    pub fn with_store(mut self, store: Arc<dyn DeliveryStore>) -> Result<Self, WebhookError> {
+        let records = store.load()?;
+        tracing::info!("Loaded {} webhook deliveries", records.len());
+        self.deliveries = Arc::new(RwLock::new(
+            records.into_iter().map(|r| (r.id, r)).collect(),
+        ));
+        self.store = store;
+        Ok(self)
+    }
+
+    /// Start the delivery worker and return the handle internal components
+    /// publish through. Due retries are picked up every `poll_interval`.
+    pub fn start(self: &Arc<Self>, poll_interval: Duration) -> EventPublisher {
+        let (tx, mut rx) = mpsc::channel::<Event>(PUBLISH_BUFFER);
+        let engine = self.clone();
+
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(poll_interval);
+            loop {
+                tokio::select! {
+                    Some(event) = rx.recv() => {
+                        if let Err(e) = engine.publish(event) {
+                            tracing::warn!("Webhook event dropped: {}", e);
+                        }
+                        engine.process().await;
+                    }
+                    _ = tick.tick() => {
+                        engine.process().await;
+                    }
+                }
+            }
+        });
+
+        EventPublisher { tx }
+    }
+
+    /// Subscribe to events, rejecting pins on unknown or retired versions
+    pub fn subscribe(&self, config: WebhookConfig) -> Result<Uuid, SchemaError> {
+        for (event_type, version) in &config.schema_versions {
+            self.schemas.check_pin(event_type, *version)?;
+        }
+
         let id = config.id;
         self.subscriptions.write().insert(id, config);
-        id
+        Ok(id)
+    }
+
+    /// Remove a subscription; its pending deliveries fail on next attempt
+    pub fn unsubscribe(&self, id: Uuid) -> Option<WebhookConfig> {
+        self.subscriptions.write().remove(&id)
+    }
+
+    /// Get subscription
+    pub fn subscription(&self, id: Uuid) -> Option<WebhookConfig> {
+        self.subscriptions.read().get(&id).cloned()
+    }
+
+    /// List subscriptions
+    pub fn subscriptions(&self) -> Vec<WebhookConfig> {
+        self.subscriptions.read().values().cloned().collect()
     }
 
     /// Publish event to all subscribers
-    pub fn publish(&self, event: Event) {
+    ///
+    /// `event.data` must be in the latest schema version of a cataloged
+    /// event type. Each subscriber receives the version it pinned (plus the
+    /// latest while the pin is deprecated). Every rendered payload is
+    /// validated before anything is queued; returns the number of queued
+    /// deliveries.
+    pub fn publish(&self, event: Event) -> Result<usize, SchemaError> {
+        let event_type = event.event_type.to_string();
+        let now = chrono::Utc::now();
+
         let subs = self.subscriptions.read();
+
+        // Legacy event types have no schema and are delivered as-is
+        if !self.schemas.contains(&event_type) {
+            return Ok(self.enqueue(&subs, &event, |_| vec![(event.clone(), None)]));
+        }
+
+        let mut rendered = HashMap::new();
+        for config in subs.values().filter(|c| c.enabled && c.events.contains(&event.event_type)) {
+            let pinned = config.schema_versions.get(&event_type).copied();
+            for version in self.schemas.versions_for(&event_type, pinned, now) {
+                if let std::collections::hash_map::Entry::Vacant(slot) = rendered.entry(version) {
+                    slot.insert(self.schemas.render(&event_type, &event.data, version)?);
+                }
+            }
+        }
+
+        Ok(self.enqueue(&subs, &event, |config| {
+            let pinned = config.schema_versions.get(&event_type).copied();
+            self.schemas.versions_for(&event_type, pinned, now)
+                .into_iter()
+                .filter_map(|v| rendered.get(&v))
+                .map(|payload| (Event {
+                    schema_version: payload.version,
+                    data: payload.data.clone(),
+                    ..event.clone()
+                }, payload.sunset_at))
+                .collect()
+        }))
+    }
+
+    fn enqueue<F>(&self, subs: &HashMap<Uuid, WebhookConfig>, event: &Event, payloads: F) -> usize
+    where
+        F: Fn(&WebhookConfig) -> Vec<(Event, Option<chrono::DateTime<chrono::Utc>>)>,
+    {
+        let mut queued = 0;
+
         for (id, config) in subs.iter() {
             if config.enabled && config.events.contains(&event.event_type) {
-                self.queue.write().push(WebhookEvent {
-                    id: Uuid::new_v4(),
-                    subscription_id: *id,
-                    event: event.clone(),
-                    attempt: 0,
-                    next_attempt: chrono::Utc::now(),
-                });
+                for (event, sunset_at) in payloads(config) {
+                    self.persist(DeliveryRecord::new(*id, event, sunset_at, None));
+                    queued += 1;
+                }
             }
         }
+
+        queued
     }
 
-    /// Process delivery queue
-    pub async fn process(&self) {
-        let events: Vec<_> = {
-            let mut queue = self.queue.write();
-            queue.drain(..).collect()
-        };
+    /// Attempt every delivery that is due; returns the number attempted
+    pub async fn process(&self) -> usize {
+        let now = Utc::now();
+        let mut due: Vec<_> = self.deliveries.read()
+            .values()
+            .filter(|r| r.is_due(now))
+            .cloned()
+            .collect();
+        due.sort_by_key(|r| r.created_at);
+
+        let mut attempted = 0;
+        for mut record in due {
+            let config = self.subscription(record.subscription_id);
 
-        for mut event in events {
-            let config = {
-                let subs = self.subscriptions.read();
-                subs.get(&event.subscription_id).cloned()
+            let Some(config) = config else {
+                record.status = DeliveryStatus::Failed;
+                record.next_attempt = None;
+                record.attempts.push(DeliveryAttempt::error(now, "subscription deleted"));
+                self.persist(record);
+                continue;
             };
+            // Paused subscriptions keep their queue until re-enabled
+            if !config.enabled {
+                continue;
+            }
 
-            if let Some(config) = config {
-                match self.deliver(&config, &event.event).await {
-                    Ok(_) => {
-                        tracing::info!("Webhook delivered: {}", event.id);
-                    }
-                    Err(e) => {
-                        event.attempt += 1;
-                        if event.attempt < config.retry_policy.max_retries {
-                            let delay = config.retry_policy.base_delay_secs * 2u64.pow(event.attempt);
-                            event.next_attempt = chrono::Utc::now() + chrono::Duration::seconds(delay as i64);
-                            self.queue.write().push(event);
-                        } else {
-                            self.dead_letter.write().push(DeadLetter {
-                                event,
-                                error: e,
-                                failed_at: chrono::Utc::now(),
-                            });
-                        }
-                    }
-                }
+            let attempt = self.deliver(&config, &record).await;
+            let succeeded = attempt.error.is_none();
+            record.attempts.push(attempt);
+            attempted += 1;
+
+            if succeeded {
+                tracing::info!("Webhook delivered: {}", record.id);
+                record.status = DeliveryStatus::Succeeded;
+                record.next_attempt = None;
+            } else if record.attempt_count() < config.retry_policy.max_retries {
+                let delay = config.retry_policy.delay(record.attempt_count());
+                record.next_attempt = Some(Utc::now() + delay);
+            } else {
+                tracing::warn!(
+                    "Webhook delivery {} to {} failed after {} attempts: {}",
+                    record.id,
+                    config.url,
+                    record.attempt_count(),
+                    record.last_error().unwrap_or_default(),
+                );
+                record.status = DeliveryStatus::Failed;
+                record.next_attempt = None;
             }
+            self.persist(record);
+        }
+
+        self.prune(now);
+        attempted
+    }
+
+    /// Send a one-off ping to a subscription without recording a delivery
+    pub async fn send_test(&self, subscription_id: Uuid) -> Result<DeliveryAttempt, WebhookError> {
+        let config = self.subscription(subscription_id)
+            .ok_or(WebhookError::UnknownSubscription(subscription_id))?;
+        let event = Event::new(EventType::Ping, Uuid::nil(), serde_json::json!({
+            "webhook_id": subscription_id,
+        }));
+        let record = DeliveryRecord::new(subscription_id, event, None, None);
+        Ok(self.deliver(&config, &record).await)
+    }
+
+    /// Queue a new delivery of a finished one's payload
+    pub fn replay(&self, delivery_id: Uuid) -> Result<DeliveryRecord, WebhookError> {
+        let original = self.delivery(delivery_id)
+            .ok_or(WebhookError::UnknownDelivery(delivery_id))?;
+        if original.status == DeliveryStatus::Pending {
+            return Err(WebhookError::StillPending(delivery_id));
+        }
+        if self.subscription(original.subscription_id).is_none() {
+            return Err(WebhookError::UnknownSubscription(original.subscription_id));
+        }
+
+        let record = DeliveryRecord::new(
+            original.subscription_id,
+            original.event,
+            original.sunset_at,
+            Some(original.id),
+        );
+        self.persist(record.clone());
+        Ok(record)
+    }
+
+    /// Replay every failed delivery of a subscription that has not already
+    /// been replayed
+    pub fn replay_failed(&self, subscription_id: Uuid) -> Result<Vec<DeliveryRecord>, WebhookError> {
+        if self.subscription(subscription_id).is_none() {
+            return Err(WebhookError::UnknownSubscription(subscription_id));
         }
+
+        let failed: Vec<Uuid> = {
+            let deliveries = self.deliveries.read();
+            let replayed: std::collections::HashSet<Uuid> = deliveries.values()
+                .filter_map(|r| r.replay_of)
+                .collect();
+            deliveries.values()
+                .filter(|r| r.subscription_id == subscription_id)
+                .filter(|r| r.status == DeliveryStatus::Failed && !replayed.contains(&r.id))
+                .map(|r| r.id)
+                .collect()
+        };
+
+        failed.into_iter().map(|id| self.replay(id)).collect()
     }
 
-    async fn deliver(&self, config: &WebhookConfig, event: &Event) -> Result<(), String> {
-        let payload = serde_json::to_string(event).map_err(|e| e.to_string())?;
-        let signature = self.sign(&payload, &config.secret);
+    /// Get delivery
+    pub fn delivery(&self, id: Uuid) -> Option<DeliveryRecord> {
+        self.deliveries.read().get(&id).cloned()
+    }
 
-        let client = reqwest::Client::new();
-        let resp = client
+    /// Deliveries for a subscription, newest first
+    pub fn deliveries(&self, subscription_id: Uuid, status: Option<DeliveryStatus>) -> Vec<DeliveryRecord> {
+        let mut records: Vec<_> = self.deliveries.read()
+            .values()
+            .filter(|r| r.subscription_id == subscription_id)
+            .filter(|r| status.is_none_or(|s| r.status == s))
+            .cloned()
+            .collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        records
+    }
+
+    async fn deliver(&self, config: &WebhookConfig, record: &DeliveryRecord) -> DeliveryAttempt {
+        let at = Utc::now();
+        let started = Instant::now();
+        let event = &record.event;
+
+        let payload = match serde_json::to_string(event) {
+            Ok(payload) => payload,
+            Err(e) => return DeliveryAttempt::error(at, e.to_string()),
+        };
+        let timestamp = at.timestamp().to_string();
+        let signature = sign(&payload, &timestamp, &config.secret);
+
+        let mut request = self.client
             .post(&config.url)
             .header("Content-Type", "application/json")
-            .header("X-OpenSASE-Signature", signature)
-            .header("X-OpenSASE-Event", &event.event_type.to_string())
-            .body(payload)
-            .timeout(Duration::from_secs(30))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+            .header(SIGNATURE_HEADER, signature)
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(DELIVERY_HEADER, record.id.to_string())
+            .header("X-OpenSASE-Event", event.event_type.to_string())
+            .header("X-OpenSASE-Schema-Version", event.schema_version.to_string());
+        if let Some(sunset_at) = record.sunset_at {
+            // RFC 8594: the pinned version stops being delivered at this time
+            request = request
+                .header("Deprecation", "true")
+                .header("Sunset", sunset_at.to_rfc2822());
+        }
+        let (status_code, error) = match request.body(payload).timeout(DELIVERY_TIMEOUT).send().await {
+            Ok(resp) if resp.status().is_success() => (Some(resp.status().as_u16()), None),
+            Ok(resp) => (Some(resp.status().as_u16()), Some(format!("HTTP {}", resp.status()))),
+            Err(e) => (None, Some(e.to_string())),
+        };
 
-        if resp.status().is_success() {
-            Ok(())
-        } else {
-            Err(format!("HTTP {}", resp.status()))
+        DeliveryAttempt {
+            at,
+            status_code,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
         }
     }
 
-    fn sign(&self, payload: &str, secret: &str) -> String {
-        use sha2::{Sha256, Digest};
-        let mut mac = Sha256::new();
-        mac.update(secret.as_bytes());
-        mac.update(payload.as_bytes());
-        format!("sha256={}", hex::encode(mac.finalize()))
+    fn persist(&self, record: DeliveryRecord) {
+        if let Err(e) = self.store.save(&record) {
+            tracing::warn!("Failed to persist webhook delivery {}: {}", record.id, e);
+        }
+        self.deliveries.write().insert(record.id, record);
+    }
+
+    /// Drop finished deliveries past retention
+    fn prune(&self, now: DateTime<Utc>) {
+        let expired: Vec<Uuid> = self.deliveries.read()
+            .values()
+            .filter(|r| {
+                let finished = r.attempts.last().map(|a| a.at).unwrap_or(r.created_at);
+                match r.status {
+                    DeliveryStatus::Pending => false,
+                    DeliveryStatus::Succeeded => now - finished > chrono::Duration::days(SUCCEEDED_RETENTION_DAYS),
+                    DeliveryStatus::Failed => now - finished > chrono::Duration::days(FAILED_RETENTION_DAYS),
+                }
+            })
+            .map(|r| r.id)
+            .collect();
+
+        if expired.is_empty() {
+            return;
+        }
+        let mut deliveries = self.deliveries.write();
+        for id in expired {
+            deliveries.remove(&id);
+            if let Err(e) = self.store.remove(id) {
+                tracing::warn!("Failed to remove webhook delivery {}: {}", id, e);
+            }
+        }
     }
 }
 
@@ -119,6 +421,140 @@ impl Default for WebhookDelivery {
     fn default() -> Self { Self::new() }
 }
 
+/// Sign a payload: `v1=<hex HMAC-SHA256 of "{timestamp}.{payload}">`
+pub fn sign(payload: &str, timestamp: &str, secret: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Verify a signature header against a payload, rejecting timestamps more
+/// than `tolerance_secs` from now
+pub fn verify_signature(
+    payload: &str,
+    signature: &str,
+    timestamp: &str,
+    secret: &str,
+    tolerance_secs: i64,
+) -> bool {
+    let Ok(ts) = timestamp.parse::<i64>() else {
+        return false;
+    };
+    if (Utc::now().timestamp() - ts).abs() > tolerance_secs {
+        return false;
+    }
+
+    signature.split(',')
+        .filter_map(|part| part.trim().strip_prefix("v1="))
+        .filter_map(|sig| hex::decode(sig).ok())
+        .any(|sig| {
+            let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+                .expect("HMAC accepts keys of any length");
+            mac.update(timestamp.as_bytes());
+            mac.update(b".");
+            mac.update(payload.as_bytes());
+            mac.verify_slice(&sig).is_ok()
+        })
+}
+
+/// Handle internal components publish events through
+#[derive(Clone)]
+pub struct EventPublisher {
+    tx: mpsc::Sender<Event>,
+}
+
+impl EventPublisher {
+    /// Hand an event to the delivery worker; never blocks the caller.
+    /// Returns false when the worker is gone or its buffer is full.
+    pub fn publish(&self, event: Event) -> bool {
+        match self.tx.try_send(event) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Webhook event not queued: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Delivery store for pending and finished deliveries
+pub trait DeliveryStore: Send + Sync {
+    /// All stored deliveries
+    fn load(&self) -> Result<Vec<DeliveryRecord>, WebhookError>;
+    /// Insert or update a delivery
+    fn save(&self, record: &DeliveryRecord) -> Result<(), WebhookError>;
+    /// Remove a delivery
+    fn remove(&self, id: Uuid) -> Result<(), WebhookError>;
+}
+
+/// Store that keeps nothing beyond the engine's own map; deliveries are
+/// lost on restart
+pub struct MemoryDeliveryStore;
+
+impl DeliveryStore for MemoryDeliveryStore {
+    fn load(&self) -> Result<Vec<DeliveryRecord>, WebhookError> { Ok(Vec::new()) }
+    fn save(&self, _record: &DeliveryRecord) -> Result<(), WebhookError> { Ok(()) }
+    fn remove(&self, _id: Uuid) -> Result<(), WebhookError> { Ok(()) }
+}
+
+/// One JSON file per delivery under a directory
+pub struct FileDeliveryStore {
+    dir: PathBuf,
+}
+
+impl FileDeliveryStore {
+    /// Store under `dir`, created if missing
+    pub fn open(dir: &Path) -> Result<Self, WebhookError> {
+        std::fs::create_dir_all(dir).map_err(|e| WebhookError::Store(e.to_string()))?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+impl DeliveryStore for FileDeliveryStore {
+    fn load(&self) -> Result<Vec<DeliveryRecord>, WebhookError> {
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| WebhookError::Store(e.to_string()))?;
+        let mut records = Vec::new();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            // A corrupt record must not block the rest of the queue
+            match std::fs::read(&path).map_err(|e| e.to_string())
+                .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+            {
+                Ok(record) => records.push(record),
+                Err(e) => tracing::warn!("Skipping webhook delivery {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(records)
+    }
+
+    fn save(&self, record: &DeliveryRecord) -> Result<(), WebhookError> {
+        let bytes = serde_json::to_vec(record).map_err(|e| WebhookError::Store(e.to_string()))?;
+        // Write then rename so a crash never leaves a truncated record
+        let tmp = self.dir.join(format!("{}.json.tmp", record.id));
+        std::fs::write(&tmp, bytes).map_err(|e| WebhookError::Store(e.to_string()))?;
+        std::fs::rename(&tmp, self.path(record.id)).map_err(|e| WebhookError::Store(e.to_string()))
+    }
+
+    fn remove(&self, id: Uuid) -> Result<(), WebhookError> {
+        match std::fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(WebhookError::Store(e.to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Webhook configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookConfig {
@@ -128,21 +564,39 @@ pub struct WebhookConfig {
     pub secret: String,
     pub retry_policy: RetryPolicy,
     pub enabled: bool,
+    /// Pinned schema version per event type; unpinned types get the latest
+    #[serde(default)]
+    pub schema_versions: HashMap<String, u32>,
 }
 
 /// Retry policy
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetryPolicy {
+    /// Attempts before a delivery is marked failed
     pub max_retries: u32,
     pub base_delay_secs: u64,
+    /// Upper bound on the backoff between attempts
+    #[serde(default = "default_max_delay_secs")]
+    pub max_delay_secs: u64,
+}
+
+impl RetryPolicy {
+    /// Delay after the given number of failed attempts, doubling each time
+    pub fn delay(&self, attempts: u32) -> chrono::Duration {
+        let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+        let secs = self.base_delay_secs.saturating_mul(factor).min(self.max_delay_secs);
+        chrono::Duration::seconds(secs as i64)
+    }
 }
 
 impl Default for RetryPolicy {
     fn default() -> Self {
-        Self { max_retries: 5, base_delay_secs: 60 }
+        Self { max_retries: 5, base_delay_secs: 60, max_delay_secs: default_max_delay_secs() }
     }
 }
 
+fn default_max_delay_secs() -> u64 { 6 * 3600 }
+
 /// Event types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EventType {
@@ -152,6 +606,11 @@ pub enum EventType {
     UserActivity,
     SystemHealth,
     TunnelStatusChanged,
+    AlertCreated,
+    InvoiceFinalized,
+    TunnelDown,
+    /// Test delivery from the management API
+    Ping,
 }
 
 impl std::fmt::Display for EventType {
@@ -163,6 +622,30 @@ impl std::fmt::Display for EventType {
             Self::UserActivity => write!(f, "user.activity"),
             Self::SystemHealth => write!(f, "system.health"),
             Self::TunnelStatusChanged => write!(f, "tunnel.status_changed"),
+            Self::AlertCreated => write!(f, "alert.created"),
+            Self::InvoiceFinalized => write!(f, "invoice.finalized"),
+            Self::TunnelDown => write!(f, "tunnel.down"),
+            Self::Ping => write!(f, "webhook.ping"),
+        }
+    }
+}
+
+impl std::str::FromStr for EventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "site.status_changed" => Ok(Self::SiteStatusChanged),
+            "security.alert" => Ok(Self::SecurityAlert),
+            "policy.changed" => Ok(Self::PolicyChanged),
+            "user.activity" => Ok(Self::UserActivity),
+            "system.health" => Ok(Self::SystemHealth),
+            "tunnel.status_changed" => Ok(Self::TunnelStatusChanged),
+            "alert.created" => Ok(Self::AlertCreated),
+            "invoice.finalized" => Ok(Self::InvoiceFinalized),
+            "tunnel.down" => Ok(Self::TunnelDown),
+            "webhook.ping" => Ok(Self::Ping),
+            other => Err(format!("unknown event type: {}", other)),
         }
     }
 }
@@ -174,23 +657,412 @@ pub struct Event {
     pub event_type: EventType,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub tenant_id: Uuid,
+    /// Schema version of `data`
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
     pub data: serde_json::Value,
 }
 
-/// Queued webhook event
-#[derive(Debug, Clone)]
-struct WebhookEvent {
-    id: Uuid,
-    subscription_id: Uuid,
-    event: Event,
-    attempt: u32,
-    next_attempt: chrono::DateTime<chrono::Utc>,
+impl Event {
+    /// New event with `data` in the latest schema version
+    pub fn new(event_type: EventType, tenant_id: Uuid, data: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type,
+            timestamp: Utc::now(),
+            tenant_id,
+            schema_version: default_schema_version(),
+            data,
+        }
+    }
 }
 
-/// Dead letter entry
-#[derive(Debug, Clone)]
-struct DeadLetter {
-    event: WebhookEvent,
-    error: String,
-    failed_at: chrono::DateTime<chrono::Utc>,
+fn default_schema_version() -> u32 { 1 }
+
+/// Delivery status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Queued or awaiting retry
+    Pending,
+    /// Receiver answered 2xx
+    Succeeded,
+    /// Retries exhausted or subscription removed
+    Failed,
+}
+
+/// One event payload to one subscription, with its attempt history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryRecord {
+    /// Delivery id, sent as `X-OpenSASE-Delivery`
+    pub id: Uuid,
+    /// Receiving subscription
+    pub subscription_id: Uuid,
+    /// Payload as rendered for the subscription's schema version
+    pub event: Event,
+    /// Sunset of the payload's schema version when it is deprecated
+    #[serde(default)]
+    pub sunset_at: Option<DateTime<Utc>>,
+    /// Current status
+    pub status: DeliveryStatus,
+    /// Attempt history, oldest first
+    pub attempts: Vec<DeliveryAttempt>,
+    /// When the next attempt is due; `None` once finished
+    pub next_attempt: Option<DateTime<Utc>>,
+    /// When the delivery was queued
+    pub created_at: DateTime<Utc>,
+    /// Delivery this one replays
+    #[serde(default)]
+    pub replay_of: Option<Uuid>,
+}
+
+impl DeliveryRecord {
+    fn new(subscription_id: Uuid, event: Event, sunset_at: Option<DateTime<Utc>>, replay_of: Option<Uuid>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            subscription_id,
+            event,
+            sunset_at,
+            status: DeliveryStatus::Pending,
+            attempts: Vec::new(),
+            next_attempt: Some(now),
+            created_at: now,
+            replay_of,
+        }
+    }
+
+    /// Pending with its next attempt at or before `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.status == DeliveryStatus::Pending && self.next_attempt.is_some_and(|at| at <= now)
+    }
+
+    /// Attempts made so far
+    pub fn attempt_count(&self) -> u32 {
+        self.attempts.len() as u32
+    }
+
+    /// Error of the most recent attempt
+    pub fn last_error(&self) -> Option<&str> {
+        self.attempts.last().and_then(|a| a.error.as_deref())
+    }
+}
+
+/// One HTTP attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    /// When the request was sent
+    pub at: DateTime<Utc>,
+    /// Response status, absent when no response was received
+    pub status_code: Option<u16>,
+    /// Failure reason; `None` on success
+    pub error: Option<String>,
+    /// Time until response or failure
+    pub duration_ms: u64,
+}
+
+impl DeliveryAttempt {
+    fn error(at: DateTime<Utc>, error: impl Into<String>) -> Self {
+        Self { at, status_code: None, error: Some(error.into()), duration_ms: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Receiver answering with `status`, keeping every request it got
+    async fn receiver(status: u16) -> (String, Arc<RwLock<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = Arc::new(RwLock::new(Vec::new()));
+        let seen = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Headers, then as much body as Content-Length says
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head.lines()
+                            .filter_map(|l| l.split_once(':'))
+                            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                seen.write().push(String::from_utf8_lossy(&request).to_string());
+                let response = format!("HTTP/1.1 {} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.split("\r\n\r\n").next()?
+            .lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim())
+    }
+
+    fn subscription(url: &str, events: Vec<EventType>) -> WebhookConfig {
+        WebhookConfig {
+            id: Uuid::new_v4(),
+            url: url.to_string(),
+            events,
+            secret: "whsec_test".to_string(),
+            // Retries are due immediately
+            retry_policy: RetryPolicy { max_retries: 2, base_delay_secs: 0, max_delay_secs: 0 },
+            enabled: true,
+            schema_versions: HashMap::new(),
+        }
+    }
+
+    fn alert() -> Event {
+        Event::new(EventType::AlertCreated, Uuid::new_v4(), json!({
+            "alert_id": "a-1",
+            "severity": "high",
+            "category": "malware",
+            "title": "Beacon to known C2",
+            "source": { "ip": "10.0.0.8", "user_id": null },
+            "mitre_techniques": ["T1071"],
+            "created_at": "2026-10-18T09:00:00Z"
+        }))
+    }
+
+    #[test]
+    fn test_signatures() {
+        let now = Utc::now().timestamp().to_string();
+        let signature = sign(r#"{"a":1}"#, &now, "secret");
+        assert!(signature.starts_with("v1="));
+        assert!(verify_signature(r#"{"a":1}"#, &signature, &now, "secret", 300));
+
+        assert!(!verify_signature(r#"{"a":2}"#, &signature, &now, "secret", 300));
+        assert!(!verify_signature(r#"{"a":1}"#, &signature, &now, "other", 300));
+        assert!(!verify_signature(r#"{"a":1}"#, "v1=zz", &now, "secret", 300));
+        assert!(!verify_signature(r#"{"a":1}"#, &signature, "yesterday", "secret", 300));
+
+        // The timestamp is signed, so an old request can't be replayed
+        let old = (Utc::now().timestamp() - 600).to_string();
+        let old_signature = sign(r#"{"a":1}"#, &old, "secret");
+        assert!(!verify_signature(r#"{"a":1}"#, &old_signature, &old, "secret", 300));
+        assert!(!verify_signature(r#"{"a":1}"#, &old_signature, &now, "secret", 300));
+
+        // Any of several signatures may match, e.g. during secret rotation
+        let rotated = format!("v1={}, {}", "00".repeat(32), signature);
+        assert!(verify_signature(r#"{"a":1}"#, &rotated, &now, "secret", 300));
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<i64> = (1..=4).map(|n| policy.delay(n).num_seconds()).collect();
+        assert_eq!(delays, vec![60, 120, 240, 480]);
+        assert_eq!(policy.delay(0).num_seconds(), 60);
+        assert_eq!(policy.delay(200).num_seconds(), 6 * 3600);
+    }
+
+    #[test]
+    fn test_event_type_names() {
+        for event_type in [
+            EventType::SiteStatusChanged, EventType::SecurityAlert, EventType::PolicyChanged,
+            EventType::UserActivity, EventType::SystemHealth, EventType::TunnelStatusChanged,
+            EventType::AlertCreated, EventType::InvoiceFinalized, EventType::TunnelDown, EventType::Ping,
+        ] {
+            assert_eq!(event_type.to_string().parse::<EventType>(), Ok(event_type));
+        }
+        assert!("alert.deleted".parse::<EventType>().is_err());
+    }
+
+    #[test]
+    fn test_publish_renders_each_subscribers_version() {
+        let webhooks = WebhookDelivery::new();
+        let mut pinned = subscription("http://pinned", vec![EventType::AlertCreated]);
+        pinned.schema_versions.insert("alert.created".to_string(), 1);
+        let pinned = webhooks.subscribe(pinned).unwrap();
+        let latest = webhooks.subscribe(subscription("http://latest", vec![EventType::AlertCreated])).unwrap();
+        let mut paused = subscription("http://paused", vec![EventType::AlertCreated]);
+        paused.enabled = false;
+        webhooks.subscribe(paused).unwrap();
+        webhooks.subscribe(subscription("http://other", vec![EventType::TunnelDown])).unwrap();
+
+        let mut bad_pin = subscription("http://bad", vec![EventType::AlertCreated]);
+        bad_pin.schema_versions.insert("alert.created".to_string(), 7);
+        assert!(matches!(webhooks.subscribe(bad_pin), Err(SchemaError::UnknownVersion(_, 7))));
+
+        assert_eq!(webhooks.publish(alert()).unwrap(), 2);
+        let v1 = &webhooks.deliveries(pinned, None)[0];
+        assert_eq!(v1.event.schema_version, 1);
+        assert!(v1.event.data.get("category").is_none());
+        assert_eq!(webhooks.deliveries(latest, None)[0].event.schema_version, 2);
+
+        // While deprecated, a pinned subscriber also gets the latest version
+        let sunset = Utc::now() + chrono::Duration::days(30);
+        webhooks.schemas.deprecate("alert.created", 1, sunset).unwrap();
+        assert_eq!(webhooks.publish(alert()).unwrap(), 3);
+        let mut versions: Vec<(u32, Option<DateTime<Utc>>)> = webhooks.deliveries(pinned, None)
+            .iter()
+            .map(|r| (r.event.schema_version, r.sunset_at))
+            .collect();
+        versions.sort();
+        assert_eq!(versions, vec![(1, None), (1, Some(sunset)), (2, None)]);
+
+        // Nothing is queued when the payload doesn't fit the schema
+        let mut invalid = alert();
+        invalid.data["severity"] = json!("urgent");
+        assert!(webhooks.publish(invalid).is_err());
+        assert_eq!(webhooks.deliveries(latest, None).len(), 2);
+
+        // Types outside the catalog go out as published
+        let legacy = Event::new(EventType::TunnelDown, Uuid::nil(), json!({ "anything": true }));
+        let schemas = Arc::new(SchemaRegistry::new());
+        let webhooks = WebhookDelivery::with_registry(schemas);
+        let id = webhooks.subscribe(subscription("http://legacy", vec![EventType::TunnelDown])).unwrap();
+        assert_eq!(webhooks.publish(legacy).unwrap(), 1);
+        assert_eq!(webhooks.deliveries(id, None)[0].event.data, json!({ "anything": true }));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_recorded() {
+        let (url, requests) = receiver(200).await;
+        let webhooks = WebhookDelivery::new();
+        let id = webhooks.subscribe(subscription(&url, vec![EventType::AlertCreated])).unwrap();
+        webhooks.publish(alert()).unwrap();
+
+        assert_eq!(webhooks.process().await, 1);
+        assert_eq!(webhooks.process().await, 0, "nothing left to send");
+
+        let record = &webhooks.deliveries(id, None)[0];
+        assert_eq!(record.status, DeliveryStatus::Succeeded);
+        assert_eq!(record.attempts.len(), 1);
+        assert_eq!(record.attempts[0].status_code, Some(200));
+        assert_eq!(record.next_attempt, None);
+
+        let request = requests.read()[0].clone();
+        let body = request.split_once("\r\n\r\n").unwrap().1;
+        assert_eq!(header(&request, DELIVERY_HEADER), Some(record.id.to_string().as_str()));
+        assert_eq!(header(&request, "X-OpenSASE-Event"), Some("alert.created"));
+        assert_eq!(header(&request, "X-OpenSASE-Schema-Version"), Some("2"));
+        let signature = header(&request, SIGNATURE_HEADER).unwrap();
+        let timestamp = header(&request, TIMESTAMP_HEADER).unwrap();
+        assert!(verify_signature(body, signature, timestamp, "whsec_test", 300));
+        assert_eq!(serde_json::from_str::<Event>(body).unwrap().id, record.event.id);
+
+        // Pings are sent but not recorded
+        let attempt = webhooks.send_test(id).await.unwrap();
+        assert!(attempt.error.is_none());
+        assert_eq!(webhooks.deliveries(id, None).len(), 1);
+        assert!(header(&requests.read()[1], "X-OpenSASE-Event") == Some("webhook.ping"));
+        assert!(matches!(webhooks.send_test(Uuid::new_v4()).await, Err(WebhookError::UnknownSubscription(_))));
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_retry_then_replay() {
+        let (url, requests) = receiver(500).await;
+        let webhooks = WebhookDelivery::new();
+        let id = webhooks.subscribe(subscription(&url, vec![EventType::AlertCreated])).unwrap();
+        webhooks.publish(alert()).unwrap();
+
+        assert_eq!(webhooks.process().await, 1);
+        let record = webhooks.deliveries(id, None)[0].clone();
+        assert_eq!(record.status, DeliveryStatus::Pending);
+        assert_eq!(record.last_error(), Some("HTTP 500 Internal Server Error"));
+        assert!(matches!(webhooks.replay(record.id), Err(WebhookError::StillPending(_))));
+
+        // Second attempt exhausts max_retries
+        assert_eq!(webhooks.process().await, 1);
+        let record = webhooks.delivery(record.id).unwrap();
+        assert_eq!(record.status, DeliveryStatus::Failed);
+        assert_eq!(record.attempt_count(), 2);
+        assert_eq!(requests.read().len(), 2);
+        // Retries reuse the delivery id so receivers can deduplicate
+        let ids: Vec<String> = requests.read().iter()
+            .map(|r| header(r, DELIVERY_HEADER).unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec![record.id.to_string(), record.id.to_string()]);
+
+        let replays = webhooks.replay_failed(id).unwrap();
+        assert_eq!(replays.len(), 1);
+        assert_eq!(replays[0].replay_of, Some(record.id));
+        assert_eq!(replays[0].event.id, record.event.id);
+        assert_ne!(replays[0].id, record.id);
+        assert!(webhooks.replay_failed(id).unwrap().is_empty(), "already replayed");
+        assert_eq!(webhooks.deliveries(id, Some(DeliveryStatus::Pending)).len(), 1);
+        assert!(matches!(webhooks.replay(Uuid::new_v4()), Err(WebhookError::UnknownDelivery(_))));
+    }
+
+    #[tokio::test]
+    async fn test_paused_and_removed_subscriptions() {
+        let webhooks = WebhookDelivery::new();
+        let mut config = subscription("http://127.0.0.1:9/hook", vec![EventType::AlertCreated]);
+        let id = webhooks.subscribe(config.clone()).unwrap();
+        webhooks.publish(alert()).unwrap();
+
+        // Pausing keeps the queue
+        config.enabled = false;
+        webhooks.subscribe(config).unwrap();
+        assert_eq!(webhooks.process().await, 0);
+        let record = webhooks.deliveries(id, None)[0].clone();
+        assert_eq!(record.status, DeliveryStatus::Pending);
+
+        webhooks.unsubscribe(id).unwrap();
+        assert_eq!(webhooks.process().await, 0);
+        let record = webhooks.delivery(record.id).unwrap();
+        assert_eq!(record.status, DeliveryStatus::Failed);
+        assert_eq!(record.last_error(), Some("subscription deleted"));
+        assert!(matches!(webhooks.replay(record.id), Err(WebhookError::UnknownSubscription(_))));
+    }
+
+    #[tokio::test]
+    async fn test_file_store_resumes_pending_deliveries() {
+        let dir = std::env::temp_dir().join(format!("webhooks-{}", Uuid::new_v4()));
+        let config = subscription("http://127.0.0.1:9/hook", vec![EventType::AlertCreated]);
+
+        let webhooks = WebhookDelivery::new()
+            .with_store(Arc::new(FileDeliveryStore::open(&dir).unwrap()))
+            .unwrap();
+        webhooks.subscribe(config.clone()).unwrap();
+        webhooks.publish(alert()).unwrap();
+        let queued = webhooks.deliveries(config.id, None)[0].clone();
+        drop(webhooks);
+
+        // A corrupt record doesn't stop the rest from loading
+        std::fs::write(dir.join(format!("{}.json", Uuid::new_v4())), b"{ not json").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let (url, requests) = receiver(204).await;
+        let webhooks = WebhookDelivery::new()
+            .with_store(Arc::new(FileDeliveryStore::open(&dir).unwrap()))
+            .unwrap();
+        webhooks.subscribe(WebhookConfig { url, ..config.clone() }).unwrap();
+        assert_eq!(webhooks.delivery(queued.id).unwrap().status, DeliveryStatus::Pending);
+
+        assert_eq!(webhooks.process().await, 1);
+        assert_eq!(requests.read().len(), 1);
+        let saved: DeliveryRecord = serde_json::from_slice(
+            &std::fs::read(dir.join(format!("{}.json", queued.id))).unwrap(),
+        ).unwrap();
+        assert_eq!(saved.status, DeliveryStatus::Succeeded);
+
+        let store = FileDeliveryStore::open(&dir).unwrap();
+        store.remove(queued.id).unwrap();
+        store.remove(queued.id).unwrap();
+        assert!(store.load().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
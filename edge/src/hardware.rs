@@ -169,3 +169,805 @@ pub struct RecommendedSettings {
     pub use_crypto_offload: bool,
     pub use_qat: bool,
 }
+
+// =============================================================================
+// Health Monitoring
+// =============================================================================
+
+/// Hardware health monitor configuration
+#[derive(Debug, Clone)]
+pub struct HardwareMonitorConfig {
+    /// Root of sysfs (overridable for containers)
+    pub sysfs_root: std::path::PathBuf,
+    /// Block devices to query with smartctl
+    pub disks: Vec<String>,
+    /// Query PSU and fan sensors over IPMI when ipmitool is present
+    pub use_ipmi: bool,
+    /// Sampling interval
+    pub interval: std::time::Duration,
+    /// Warn this many degrees below a sensor's critical threshold
+    pub temp_headroom_c: f32,
+    /// Failure score at which an RMA is recommended
+    pub rma_threshold: f32,
+}
+
+impl Default for HardwareMonitorConfig {
+    fn default() -> Self {
+        Self {
+            sysfs_root: "/sys".into(),
+            disks: vec!["/dev/sda".into()],
+            use_ipmi: true,
+            interval: std::time::Duration::from_secs(60),
+            temp_headroom_c: 10.0,
+            rma_threshold: 0.7,
+        }
+    }
+}
+
+/// Point-in-time hardware health readings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HardwareSnapshot {
+    pub disks: Vec<DiskHealth>,
+    pub temperatures: Vec<TemperatureReading>,
+    pub fans: Vec<FanReading>,
+    pub psus: Vec<PsuReading>,
+    pub timestamp: u64,
+}
+
+/// SMART health for one disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskHealth {
+    pub device: String,
+    pub model: Option<String>,
+    pub smart_passed: bool,
+    pub attributes: Vec<SmartAttribute>,
+    pub temperature_c: Option<f32>,
+    /// Predicted likelihood of failure (0.0 - 1.0)
+    pub failure_score: f32,
+}
+
+/// Raw SMART attribute
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartAttribute {
+    pub id: u8,
+    pub name: String,
+    pub value: u8,
+    pub threshold: u8,
+    pub raw: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemperatureReading {
+    pub sensor: String,
+    pub celsius: f32,
+    pub critical_c: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanReading {
+    pub fan: String,
+    pub rpm: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PsuReading {
+    pub psu: String,
+    pub ok: bool,
+    pub detail: Option<String>,
+}
+
+/// Hardware alert raised to the controller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwareAlert {
+    pub component: HardwareComponent,
+    pub name: String,
+    pub severity: AlertSeverity,
+    pub message: String,
+    /// Set when the component is predicted to fail and should be replaced
+    pub rma_recommended: bool,
+    pub raised_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HardwareComponent {
+    Disk,
+    Temperature,
+    Fan,
+    Psu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AlertSeverity {
+    Warning,
+    Critical,
+}
+
+/// SMART attributes that predict drive failure, with weights
+///
+/// Any non-zero raw count of these is a strong signal; weights reflect how
+/// sharply each one correlates with failure in published field studies.
+const PREDICTIVE_SMART_ATTRS: &[(u8, f32)] = &[
+    (5, 0.35),   // Reallocated sectors
+    (187, 0.30), // Reported uncorrectable
+    (188, 0.10), // Command timeout
+    (197, 0.30), // Current pending sectors
+    (198, 0.35), // Offline uncorrectable
+];
+
+/// Monitors disks, temperatures, fans and PSUs
+pub struct HardwareMonitor {
+    config: HardwareMonitorConfig,
+    latest: parking_lot::RwLock<Option<HardwareSnapshot>>,
+    /// Previous SMART raw counters per disk, for trend detection
+    smart_history: parking_lot::RwLock<std::collections::HashMap<String, std::collections::HashMap<u8, u64>>>,
+    /// Active alerts keyed by component and name
+    alerts: parking_lot::RwLock<std::collections::HashMap<(HardwareComponent, String), HardwareAlert>>,
+    /// Fans that have reported a non-zero speed; a header that has only ever
+    /// read 0 RPM has no fan fitted
+    spinning_fans: parking_lot::RwLock<std::collections::HashSet<String>>,
+}
+
+impl HardwareMonitor {
+    pub fn new(config: HardwareMonitorConfig) -> Self {
+        Self {
+            config,
+            latest: parking_lot::RwLock::new(None),
+            smart_history: parking_lot::RwLock::new(std::collections::HashMap::new()),
+            alerts: parking_lot::RwLock::new(std::collections::HashMap::new()),
+            spinning_fans: parking_lot::RwLock::new(std::collections::HashSet::new()),
+        }
+    }
+
+    /// Latest snapshot, if one has been collected
+    pub fn latest(&self) -> Option<HardwareSnapshot> {
+        self.latest.read().clone()
+    }
+
+    /// Currently active alerts
+    pub fn active_alerts(&self) -> Vec<HardwareAlert> {
+        self.alerts.read().values().cloned().collect()
+    }
+
+    /// Read all sensors
+    pub fn collect(&self) -> HardwareSnapshot {
+        let mut temperatures = Vec::new();
+        let mut fans = Vec::new();
+        self.read_hwmon(&mut temperatures, &mut fans);
+
+        let mut psus = self.read_power_supplies();
+        if self.config.use_ipmi {
+            psus.extend(read_ipmi_psus());
+        }
+
+        let disks = self.config.disks.iter()
+            .filter_map(|device| read_smart(device))
+            .map(|disk| self.score_disk(disk))
+            .collect();
+
+        HardwareSnapshot {
+            disks,
+            temperatures,
+            fans,
+            psus,
+            timestamp: now(),
+        }
+    }
+
+    /// Evaluate a snapshot; returns alerts that are new or escalated
+    pub fn evaluate(&self, snapshot: &HardwareSnapshot) -> Vec<HardwareAlert> {
+        let mut current = Vec::new();
+
+        for disk in &snapshot.disks {
+            if !disk.smart_passed {
+                current.push(alert(HardwareComponent::Disk, &disk.device, AlertSeverity::Critical,
+                    "SMART overall health check failed".into(), true));
+            } else if disk.failure_score >= self.config.rma_threshold {
+                current.push(alert(HardwareComponent::Disk, &disk.device, AlertSeverity::Critical,
+                    format!("Predicted disk failure (score {:.2})", disk.failure_score), true));
+            } else if disk.failure_score > 0.0 {
+                current.push(alert(HardwareComponent::Disk, &disk.device, AlertSeverity::Warning,
+                    format!("Disk degrading (score {:.2})", disk.failure_score), false));
+            }
+        }
+
+        for temp in &snapshot.temperatures {
+            let Some(critical) = temp.critical_c else { continue };
+            if temp.celsius >= critical {
+                current.push(alert(HardwareComponent::Temperature, &temp.sensor, AlertSeverity::Critical,
+                    format!("{:.1}°C at or above critical {:.1}°C", temp.celsius, critical), false));
+            } else if temp.celsius >= critical - self.config.temp_headroom_c {
+                current.push(alert(HardwareComponent::Temperature, &temp.sensor, AlertSeverity::Warning,
+                    format!("{:.1}°C within {:.0}°C of critical", temp.celsius, self.config.temp_headroom_c), false));
+            }
+        }
+
+        {
+            let mut spinning = self.spinning_fans.write();
+            for fan in &snapshot.fans {
+                if fan.rpm > 0 {
+                    spinning.insert(fan.fan.clone());
+                } else if spinning.contains(&fan.fan) {
+                    current.push(alert(HardwareComponent::Fan, &fan.fan, AlertSeverity::Critical,
+                        "Fan stopped".into(), true));
+                }
+            }
+        }
+
+        for psu in &snapshot.psus {
+            if !psu.ok {
+                current.push(alert(HardwareComponent::Psu, &psu.psu, AlertSeverity::Critical,
+                    psu.detail.clone().unwrap_or_else(|| "Power supply fault".into()), true));
+            }
+        }
+
+        // Only report alerts that are new or have escalated; clear resolved ones
+        let mut active = self.alerts.write();
+        let mut raised = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+
+        for a in current {
+            let key = (a.component, a.name.clone());
+            seen.insert(key.clone());
+            let escalated = active.get(&key)
+                .map(|prev| a.severity > prev.severity || (a.rma_recommended && !prev.rma_recommended))
+                .unwrap_or(true);
+            if escalated {
+                raised.push(a.clone());
+                active.insert(key, a);
+            }
+        }
+
+        active.retain(|key, a| {
+            let keep = seen.contains(key);
+            if !keep {
+                tracing::info!("Hardware alert cleared: {:?} {}", a.component, a.name);
+            }
+            keep
+        });
+
+        raised
+    }
+
+    /// Collect, evaluate and store a snapshot
+    pub fn poll(&self) -> Vec<HardwareAlert> {
+        let snapshot = self.collect();
+        let raised = self.evaluate(&snapshot);
+        *self.latest.write() = Some(snapshot);
+        raised
+    }
+
+    /// Predictive failure score from SMART attributes and their trend
+    fn score_disk(&self, mut disk: DiskHealth) -> DiskHealth {
+        let mut history = self.smart_history.write();
+        let previous = history.entry(disk.device.clone()).or_default();
+        let mut score = 0.0f32;
+
+        for attr in &disk.attributes {
+            if let Some((_, weight)) = PREDICTIVE_SMART_ATTRS.iter().find(|(id, _)| *id == attr.id) {
+                if attr.raw > 0 {
+                    score += weight;
+                    // Counters still growing since the last sample mean active degradation
+                    if previous.get(&attr.id).map(|p| attr.raw > *p).unwrap_or(false) {
+                        score += weight / 2.0;
+                    }
+                }
+                previous.insert(attr.id, attr.raw);
+            }
+
+            // Normalized value at or below the vendor threshold is a failing attribute
+            if attr.threshold > 0 && attr.value <= attr.threshold {
+                score += 0.5;
+            }
+        }
+
+        disk.failure_score = score.min(1.0);
+        disk
+    }
+
+    /// Read temperatures and fans from /sys/class/hwmon
+    fn read_hwmon(&self, temperatures: &mut Vec<TemperatureReading>, fans: &mut Vec<FanReading>) {
+        let hwmon = self.config.sysfs_root.join("class/hwmon");
+        let Ok(entries) = std::fs::read_dir(&hwmon) else { return };
+
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let chip = read_trimmed(&dir.join("name")).unwrap_or_else(|| entry.file_name().to_string_lossy().into());
+            let Ok(files) = std::fs::read_dir(&dir) else { continue };
+
+            for file in files.flatten() {
+                let file_name = file.file_name().to_string_lossy().to_string();
+                let Some(channel) = file_name.strip_suffix("_input") else { continue };
+                let label = read_trimmed(&dir.join(format!("{}_label", channel)))
+                    .unwrap_or_else(|| channel.to_string());
+                let sensor = format!("{}/{}", chip, label);
+
+                if channel.starts_with("temp") {
+                    if let Some(milli) = read_number(&file.path()) {
+                        temperatures.push(TemperatureReading {
+                            sensor,
+                            celsius: milli as f32 / 1000.0,
+                            critical_c: read_number(&dir.join(format!("{}_crit", channel)))
+                                .map(|c| c as f32 / 1000.0),
+                        });
+                    }
+                } else if channel.starts_with("fan") {
+                    if let Some(rpm) = read_number(&file.path()) {
+                        fans.push(FanReading { fan: sensor, rpm: rpm as u32 });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read mains supplies from /sys/class/power_supply
+    fn read_power_supplies(&self) -> Vec<PsuReading> {
+        let dir = self.config.sysfs_root.join("class/power_supply");
+        let Ok(entries) = std::fs::read_dir(&dir) else { return Vec::new() };
+
+        entries.flatten()
+            .filter(|e| read_trimmed(&e.path().join("type")).as_deref() == Some("Mains"))
+            .map(|e| {
+                let online = read_number(&e.path().join("online")).unwrap_or(0) == 1;
+                PsuReading {
+                    psu: e.file_name().to_string_lossy().into(),
+                    ok: online,
+                    detail: (!online).then(|| "Input power lost".to_string()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Start the hardware monitor loop
+pub async fn start_monitor(
+    monitor: std::sync::Arc<HardwareMonitor>,
+    metrics: std::sync::Arc<crate::metrics::MetricsCollector>,
+    reporter: std::sync::Arc<AlertReporter>,
+) -> Result<(), crate::EdgeError> {
+    tracing::info!("Starting hardware monitor");
+
+    let mut interval = tokio::time::interval(monitor.config.interval);
+
+    loop {
+        interval.tick().await;
+
+        let m = monitor.clone();
+        let raised = tokio::task::spawn_blocking(move || m.poll())
+            .await
+            .unwrap_or_default();
+
+        if let Some(snapshot) = monitor.latest() {
+            metrics.update_hardware(snapshot);
+        }
+
+        for alert in &raised {
+            match alert.severity {
+                AlertSeverity::Critical => tracing::error!("{:?} {}: {}", alert.component, alert.name, alert.message),
+                AlertSeverity::Warning => tracing::warn!("{:?} {}: {}", alert.component, alert.name, alert.message),
+            }
+        }
+
+        // Also runs with nothing new, to retry alerts the controller missed
+        if let Err(e) = reporter.report(&raised).await {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+/// Most undelivered alerts kept while the controller is unreachable
+const MAX_PENDING_ALERTS: usize = 1000;
+
+/// Delivers raised alerts to the controller, which opens an RMA when one is
+/// recommended
+///
+/// An alert is only raised once, so batches that fail to send are kept and
+/// retried with the next report rather than dropped.
+pub struct AlertReporter {
+    url: String,
+    client: reqwest::Client,
+    pending: parking_lot::Mutex<Vec<HardwareAlert>>,
+}
+
+impl AlertReporter {
+    /// Report to the site's hardware alert endpoint on the controller
+    pub fn new(config: &crate::EdgeConfig) -> Self {
+        Self::with_url(format!(
+            "{}/api/v1/tenants/{}/sites/{}/hardware-alerts",
+            config.controller_url.trim_end_matches('/'),
+            config.tenant_id,
+            config.site_id
+        ))
+    }
+
+    fn with_url(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            pending: parking_lot::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Alerts waiting to be delivered
+    pub fn pending(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    /// Send new alerts together with any still undelivered
+    pub async fn report(&self, alerts: &[HardwareAlert]) -> Result<(), crate::EdgeError> {
+        let mut batch = std::mem::take(&mut *self.pending.lock());
+        batch.extend_from_slice(alerts);
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let sent = self.client.post(&self.url)
+            .json(&batch)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+
+        match sent {
+            Ok(_) => {
+                let rma = batch.iter().filter(|a| a.rma_recommended).count();
+                tracing::info!("Reported {} hardware alerts to controller ({} RMA candidates)", batch.len(), rma);
+                Ok(())
+            }
+            Err(e) => {
+                // Keep the newest if the outage outlasts the backlog
+                let excess = batch.len().saturating_sub(MAX_PENDING_ALERTS);
+                batch.drain(..excess);
+                let count = batch.len();
+                *self.pending.lock() = batch;
+                Err(crate::EdgeError::Network(format!(
+                    "hardware alert report failed, {} pending: {}", count, e
+                )))
+            }
+        }
+    }
+}
+
+fn alert(component: HardwareComponent, name: &str, severity: AlertSeverity, message: String, rma_recommended: bool) -> HardwareAlert {
+    HardwareAlert {
+        component,
+        name: name.to_string(),
+        severity,
+        message,
+        rma_recommended,
+        raised_at: now(),
+    }
+}
+
+/// Query a disk with `smartctl --json`
+fn read_smart(device: &str) -> Option<DiskHealth> {
+    let output = std::process::Command::new("smartctl")
+        .args(["--json", "-H", "-A", "-i", device])
+        .output()
+        .ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    parse_smart(device, &json)
+}
+
+fn parse_smart(device: &str, json: &serde_json::Value) -> Option<DiskHealth> {
+    let smart_passed = json["smart_status"]["passed"].as_bool()?;
+
+    let attributes = json["ata_smart_attributes"]["table"].as_array()
+        .map(|table| table.iter().filter_map(|a| Some(SmartAttribute {
+            id: a["id"].as_u64()? as u8,
+            name: a["name"].as_str().unwrap_or_default().to_string(),
+            value: a["value"].as_u64().unwrap_or(0) as u8,
+            threshold: a["thresh"].as_u64().unwrap_or(0) as u8,
+            raw: a["raw"]["value"].as_u64().unwrap_or(0),
+        })).collect())
+        .unwrap_or_default();
+
+    Some(DiskHealth {
+        device: device.to_string(),
+        model: json["model_name"].as_str().map(String::from),
+        smart_passed,
+        attributes,
+        temperature_c: json["temperature"]["current"].as_f64().map(|t| t as f32),
+        failure_score: 0.0,
+    })
+}
+
+/// Query power supplies with `ipmitool sdr type "Power Supply"`
+fn read_ipmi_psus() -> Vec<PsuReading> {
+    let Ok(output) = std::process::Command::new("ipmitool")
+        .args(["sdr", "type", "Power Supply"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_ipmi_sdr_line)
+        .collect()
+}
+
+/// Parse `PS1 Status | C8h | ok | 10.1 | Presence detected, Failure detected`
+fn parse_ipmi_sdr_line(line: &str) -> Option<PsuReading> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+    if fields.len() < 5 || fields[2] == "ns" {
+        return None;
+    }
+
+    let events = fields[4].to_lowercase();
+    let failed = events.contains("failure") || events.contains("ac lost") || events.contains("predictive");
+
+    Some(PsuReading {
+        psu: fields[0].to_string(),
+        ok: fields[2] == "ok" && !failed,
+        detail: failed.then(|| fields[4].to_string()),
+    })
+}
+
+fn read_trimmed(path: &std::path::Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_number(path: &std::path::Path) -> Option<i64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `smartctl --json -H -A -i /dev/sda` from a SATA SSD with a few
+    /// reallocated sectors, trimmed to the fields we read
+    const SMARTCTL_SATA: &str = r#"{
+      "json_format_version": [1, 0],
+      "smartctl": { "version": [7, 3], "exit_status": 0 },
+      "device": { "name": "/dev/sda", "info_name": "/dev/sda [SAT]", "type": "sat", "protocol": "ATA" },
+      "model_name": "Samsung SSD 860 EVO 500GB",
+      "serial_number": "S3Z2NB0K123456A",
+      "smart_status": { "passed": true },
+      "ata_smart_attributes": {
+        "revision": 1,
+        "table": [
+          { "id": 5, "name": "Reallocated_Sector_Ct", "value": 99, "worst": 99, "thresh": 10, "when_failed": "",
+            "flags": { "value": 51, "string": "PO--CK " }, "raw": { "value": 8, "string": "8" } },
+          { "id": 9, "name": "Power_On_Hours", "value": 95, "worst": 95, "thresh": 0, "when_failed": "",
+            "flags": { "value": 50, "string": "-O--CK " }, "raw": { "value": 21843, "string": "21843" } },
+          { "id": 187, "name": "Reported_Uncorrect", "value": 100, "worst": 100, "thresh": 0, "when_failed": "",
+            "flags": { "value": 50, "string": "-O--CK " }, "raw": { "value": 0, "string": "0" } },
+          { "id": 194, "name": "Temperature_Celsius", "value": 67, "worst": 52, "thresh": 0, "when_failed": "",
+            "flags": { "value": 50, "string": "-O---K " }, "raw": { "value": 33, "string": "33 (Min/Max 18/48)" } }
+        ]
+      },
+      "temperature": { "current": 33 }
+    }"#;
+
+    /// NVMe drives report health and temperature but no ATA attribute table
+    const SMARTCTL_NVME: &str = r#"{
+      "device": { "name": "/dev/nvme0", "type": "nvme", "protocol": "NVMe" },
+      "model_name": "INTEL SSDPEKNW512G8",
+      "smart_status": { "passed": false, "nvme": { "value": 4 } },
+      "nvme_smart_health_information_log": { "critical_warning": 4, "percentage_used": 103 },
+      "temperature": { "current": 41 }
+    }"#;
+
+    /// `smartctl` failing to open the device
+    const SMARTCTL_ERROR: &str = r#"{
+      "smartctl": { "exit_status": 2, "messages": [
+        { "string": "Smartctl open device: /dev/sdb failed: No such device", "severity": "error" }
+      ] }
+    }"#;
+
+    /// `ipmitool sdr type "Power Supply"`
+    const IPMI_SDR: &str = "\
+PS1 Status       | C8h | ok  | 10.1 | Presence detected
+PS2 Status       | C9h | ok  | 10.2 | Presence detected, Power Supply AC lost
+PS Redundancy    | 77h | ok  | 21.1 | Fully Redundant
+PS3 Status       | CAh | ns  | 10.3 | No Reading
+PS4 Status       | CBh | cr  | 10.4 | Presence detected, Failure detected
+Unable to send command";
+
+    fn monitor() -> HardwareMonitor {
+        HardwareMonitor::new(HardwareMonitorConfig::default())
+    }
+
+    fn attr(id: u8, value: u8, threshold: u8, raw: u64) -> SmartAttribute {
+        SmartAttribute { id, name: String::new(), value, threshold, raw }
+    }
+
+    fn disk(attributes: Vec<SmartAttribute>) -> DiskHealth {
+        DiskHealth {
+            device: "/dev/sda".into(),
+            model: None,
+            smart_passed: true,
+            attributes,
+            temperature_c: None,
+            failure_score: 0.0,
+        }
+    }
+
+    fn fans(rpm: u32) -> HardwareSnapshot {
+        HardwareSnapshot {
+            fans: vec![
+                FanReading { fan: "nct6775/fan1".into(), rpm },
+                // Unpopulated header
+                FanReading { fan: "nct6775/fan3".into(), rpm: 0 },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_smart() {
+        let sata = parse_smart("/dev/sda", &serde_json::from_str(SMARTCTL_SATA).unwrap()).unwrap();
+        assert!(sata.smart_passed);
+        assert_eq!(sata.model.as_deref(), Some("Samsung SSD 860 EVO 500GB"));
+        assert_eq!(sata.temperature_c, Some(33.0));
+        assert_eq!(sata.attributes.len(), 4);
+        let reallocated = &sata.attributes[0];
+        assert_eq!((reallocated.id, reallocated.value, reallocated.threshold, reallocated.raw), (5, 99, 10, 8));
+        assert_eq!(reallocated.name, "Reallocated_Sector_Ct");
+
+        let nvme = parse_smart("/dev/nvme0", &serde_json::from_str(SMARTCTL_NVME).unwrap()).unwrap();
+        assert!(!nvme.smart_passed);
+        assert!(nvme.attributes.is_empty());
+        assert_eq!(nvme.temperature_c, Some(41.0));
+
+        assert!(parse_smart("/dev/sdb", &serde_json::from_str(SMARTCTL_ERROR).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_parse_ipmi_sdr() {
+        let psus: Vec<PsuReading> = IPMI_SDR.lines().filter_map(parse_ipmi_sdr_line).collect();
+        let summary: Vec<(&str, bool)> = psus.iter().map(|p| (p.psu.as_str(), p.ok)).collect();
+        assert_eq!(summary, vec![
+            ("PS1 Status", true),
+            ("PS2 Status", false),
+            ("PS Redundancy", true),
+            ("PS4 Status", false),
+        ]);
+        assert_eq!(psus[1].detail.as_deref(), Some("Presence detected, Power Supply AC lost"));
+        assert_eq!(psus[3].detail.as_deref(), Some("Presence detected, Failure detected"));
+        assert_eq!(psus[0].detail, None);
+    }
+
+    #[test]
+    fn test_score_disk() {
+        let monitor = monitor();
+
+        let healthy = monitor.score_disk(DiskHealth {
+            device: "/dev/sdb".into(),
+            ..disk(vec![attr(5, 100, 10, 0), attr(9, 95, 0, 21843), attr(187, 100, 0, 0)])
+        });
+        assert_eq!(healthy.failure_score, 0.0);
+
+        // A non-zero reallocated count, then growth since the last sample
+        let first = monitor.score_disk(disk(vec![attr(5, 99, 10, 8)]));
+        assert!((first.failure_score - 0.35).abs() < 1e-6);
+        let growing = monitor.score_disk(disk(vec![attr(5, 98, 10, 12)]));
+        assert!((growing.failure_score - 0.525).abs() < 1e-6);
+        let steady = monitor.score_disk(disk(vec![attr(5, 98, 10, 12)]));
+        assert!((steady.failure_score - 0.35).abs() < 1e-6);
+
+        // A normalized value at its threshold fails regardless of raw count
+        let failing = monitor.score_disk(disk(vec![attr(1, 6, 6, 0)]));
+        assert!((failing.failure_score - 0.5).abs() < 1e-6);
+
+        let many = monitor.score_disk(disk(vec![attr(5, 90, 10, 1), attr(197, 100, 0, 4), attr(198, 100, 0, 4)]));
+        assert_eq!(many.failure_score, 1.0);
+    }
+
+    #[test]
+    fn test_evaluate_disks_temperatures_and_psus() {
+        let monitor = monitor();
+        let mut failed = disk(Vec::new());
+        failed.device = "/dev/sdb".into();
+        failed.smart_passed = false;
+        let snapshot = HardwareSnapshot {
+            disks: vec![
+                DiskHealth { failure_score: 0.35, ..disk(Vec::new()) },
+                failed,
+            ],
+            temperatures: vec![
+                TemperatureReading { sensor: "coretemp/Core 0".into(), celsius: 92.0, critical_c: Some(100.0) },
+                TemperatureReading { sensor: "coretemp/Core 1".into(), celsius: 101.0, critical_c: Some(100.0) },
+                TemperatureReading { sensor: "coretemp/Core 2".into(), celsius: 60.0, critical_c: Some(100.0) },
+                TemperatureReading { sensor: "acpitz/temp1".into(), celsius: 120.0, critical_c: None },
+            ],
+            psus: vec![PsuReading { psu: "PS2 Status".into(), ok: false, detail: None }],
+            ..Default::default()
+        };
+
+        let mut raised = monitor.evaluate(&snapshot);
+        raised.sort_by(|a, b| a.name.cmp(&b.name));
+        let summary: Vec<(&str, AlertSeverity, bool)> = raised.iter()
+            .map(|a| (a.name.as_str(), a.severity, a.rma_recommended))
+            .collect();
+        assert_eq!(summary, vec![
+            ("/dev/sda", AlertSeverity::Warning, false),
+            ("/dev/sdb", AlertSeverity::Critical, true),
+            ("PS2 Status", AlertSeverity::Critical, true),
+            ("coretemp/Core 0", AlertSeverity::Warning, false),
+            ("coretemp/Core 1", AlertSeverity::Critical, false),
+        ]);
+        assert_eq!(raised[2].message, "Power supply fault");
+    }
+
+    #[test]
+    fn test_evaluate_raises_once_and_escalates() {
+        let monitor = monitor();
+        let warm = |celsius| HardwareSnapshot {
+            temperatures: vec![TemperatureReading { sensor: "coretemp/Core 0".into(), celsius, critical_c: Some(100.0) }],
+            ..Default::default()
+        };
+
+        assert_eq!(monitor.evaluate(&warm(95.0)).len(), 1);
+        assert!(monitor.evaluate(&warm(96.0)).is_empty());
+        let escalated = monitor.evaluate(&warm(100.0));
+        assert_eq!(escalated[0].severity, AlertSeverity::Critical);
+        assert_eq!(monitor.active_alerts().len(), 1);
+
+        assert!(monitor.evaluate(&warm(50.0)).is_empty());
+        assert!(monitor.active_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_stopped_fan_needs_prior_speed() {
+        let monitor = monitor();
+
+        // Nothing has spun yet: both 0 RPM headers are treated as empty
+        assert!(monitor.evaluate(&fans(0)).is_empty());
+        assert!(monitor.evaluate(&fans(1200)).is_empty());
+
+        let raised = monitor.evaluate(&fans(0));
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].name, "nct6775/fan1");
+        assert_eq!(raised[0].component, HardwareComponent::Fan);
+        assert!(raised[0].rma_recommended);
+
+        assert!(monitor.evaluate(&fans(1150)).is_empty());
+        assert!(monitor.active_alerts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reporter_retries_undelivered_alerts() {
+        use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+        use std::sync::Arc;
+
+        type Received = Arc<parking_lot::Mutex<Vec<Vec<String>>>>;
+        async fn receive(State(received): State<Received>, Json(batch): Json<Vec<HardwareAlert>>) -> StatusCode {
+            let mut received = received.lock();
+            received.push(batch.into_iter().map(|a| a.name).collect());
+            // The controller is down for the first delivery
+            if received.len() == 1 { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::ACCEPTED }
+        }
+
+        let received = Received::default();
+        let app = Router::new().route("/alerts", post(receive)).with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let reporter = AlertReporter::with_url(url);
+        let fan = alert(HardwareComponent::Fan, "nct6775/fan1", AlertSeverity::Critical, "Fan stopped".into(), true);
+        let psu = alert(HardwareComponent::Psu, "PS2 Status", AlertSeverity::Critical, "AC lost".into(), true);
+
+        assert!(reporter.report(&[fan]).await.is_err());
+        assert_eq!(reporter.pending(), 1);
+        reporter.report(&[psu]).await.unwrap();
+        assert_eq!(reporter.pending(), 0);
+        reporter.report(&[]).await.unwrap();
+
+        assert_eq!(*received.lock(), vec![
+            vec!["nct6775/fan1".to_string()],
+            vec!["nct6775/fan1".to_string(), "PS2 Status".to_string()],
+        ]);
+    }
+}
@@ -82,6 +82,10 @@ pub struct OpenSASEEdge {
     pub security: Arc<SecurityStack>,
     /// Tunnel manager
     pub tunnels: Arc<TunnelManager>,
+    /// Appliance hardware health
+    pub hardware: Arc<hardware::HardwareMonitor>,
+    /// Metrics collector
+    pub metrics: Arc<metrics::MetricsCollector>,
     /// State
     state: Arc<RwLock<EdgeState>>,
 }
@@ -95,6 +99,8 @@ impl OpenSASEEdge {
             sdwan: Arc::new(SdwanController::new()),
             security: Arc::new(SecurityStack::new()),
             tunnels: Arc::new(TunnelManager::new()),
+            hardware: Arc::new(hardware::HardwareMonitor::new(hardware::HardwareMonitorConfig::default())),
+            metrics: Arc::new(metrics::MetricsCollector::new()),
             state: Arc::new(RwLock::new(EdgeState::Initializing)),
         }
     }
@@ -130,10 +136,13 @@ impl OpenSASEEdge {
     pub async fn run(&self) -> Result<(), EdgeError> {
         let api_handle = self.start_api_server();
         let health_handle = self.start_health_monitor();
+        let reporter = Arc::new(hardware::AlertReporter::new(&self.config.read()));
+        let hardware_handle = hardware::start_monitor(self.hardware.clone(), self.metrics.clone(), reporter);
         
         tokio::select! {
             _ = api_handle => {},
             _ = health_handle => {},
+            _ = hardware_handle => {},
         }
         
         Ok(())
@@ -3,12 +3,14 @@
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use parking_lot::RwLock;
+use crate::hardware::HardwareSnapshot;
 
 /// Metrics collector
 pub struct MetricsCollector {
     system: Arc<RwLock<SystemMetrics>>,
     network: Arc<RwLock<Vec<InterfaceMetrics>>>,
     tunnel: Arc<RwLock<Vec<TunnelMetrics>>>,
+    hardware: Arc<RwLock<Option<HardwareSnapshot>>>,
 }
 
 impl MetricsCollector {
@@ -17,6 +19,7 @@ impl MetricsCollector {
             system: Arc::new(RwLock::new(SystemMetrics::default())),
             network: Arc::new(RwLock::new(Vec::new())),
             tunnel: Arc::new(RwLock::new(Vec::new())),
+            hardware: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -26,6 +29,7 @@ impl MetricsCollector {
             system: self.collect_system(),
             interfaces: self.network.read().clone(),
             tunnels: self.tunnel.read().clone(),
+            hardware: self.hardware.read().clone(),
             timestamp: now(),
         }
     }
@@ -65,6 +69,11 @@ impl MetricsCollector {
         }
     }
 
+    /// Update hardware health readings
+    pub fn update_hardware(&self, snapshot: HardwareSnapshot) {
+        *self.hardware.write() = Some(snapshot);
+    }
+
     /// Export as Prometheus format
     pub fn prometheus(&self) -> String {
         let metrics = self.collect();
@@ -78,14 +87,44 @@ impl MetricsCollector {
         
         for iface in &metrics.interfaces {
             output.push_str(&format!("interface_rx_bytes{{name=\"{}\"}} {}\n", 
-                iface.name, iface.rx_bytes));
+                label(&iface.name), iface.rx_bytes));
             output.push_str(&format!("interface_tx_bytes{{name=\"{}\"}} {}\n", 
-                iface.name, iface.tx_bytes));
+                label(&iface.name), iface.tx_bytes));
         }
         
         for tunnel in &metrics.tunnels {
             output.push_str(&format!("tunnel_latency_ms{{pop=\"{}\"}} {}\n", 
-                tunnel.pop_id, tunnel.latency_ms));
+                label(&tunnel.pop_id), tunnel.latency_ms));
+        }
+        
+        if let Some(hw) = &metrics.hardware {
+            output.push_str("# HELP hw_temperature_celsius Sensor temperature\n");
+            for t in &hw.temperatures {
+                output.push_str(&format!("hw_temperature_celsius{{sensor=\"{}\"}} {}\n", 
+                    label(&t.sensor), t.celsius));
+            }
+            
+            output.push_str("# HELP hw_fan_rpm Fan speed\n");
+            for f in &hw.fans {
+                output.push_str(&format!("hw_fan_rpm{{fan=\"{}\"}} {}\n", label(&f.fan), f.rpm));
+            }
+            
+            output.push_str("# HELP hw_psu_ok Power supply healthy (1) or faulted (0)\n");
+            for p in &hw.psus {
+                output.push_str(&format!("hw_psu_ok{{psu=\"{}\"}} {}\n", label(&p.psu), p.ok as u8));
+            }
+            
+            output.push_str("# HELP hw_disk_failure_score Predicted disk failure likelihood\n");
+            for d in &hw.disks {
+                output.push_str(&format!("hw_disk_failure_score{{disk=\"{}\"}} {}\n", 
+                    label(&d.device), d.failure_score));
+                output.push_str(&format!("hw_disk_smart_passed{{disk=\"{}\"}} {}\n", 
+                    label(&d.device), d.smart_passed as u8));
+                for a in &d.attributes {
+                    output.push_str(&format!("hw_disk_smart_raw{{disk=\"{}\",id=\"{}\",name=\"{}\"}} {}\n", 
+                        label(&d.device), a.id, label(&a.name), a.raw));
+                }
+            }
         }
         
         output
@@ -102,6 +141,7 @@ pub struct AllMetrics {
     pub system: SystemMetrics,
     pub interfaces: Vec<InterfaceMetrics>,
     pub tunnels: Vec<TunnelMetrics>,
+    pub hardware: Option<HardwareSnapshot>,
     pub timestamp: u64,
 }
 
@@ -144,9 +184,46 @@ pub struct TunnelMetrics {
     pub last_handshake: u64,
 }
 
+/// Escape a Prometheus label value; sensor and SMART attribute names come
+/// from hardware and may contain quotes or backslashes
+fn label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 fn now() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::{FanReading, TemperatureReading};
+
+    #[test]
+    fn test_prometheus_escapes_labels() {
+        assert_eq!(label(r#"nct6775/CPU "Core" 0\1"#), r#"nct6775/CPU \"Core\" 0\\1"#);
+        assert_eq!(label("a\nb"), "a\\nb");
+
+        let metrics = MetricsCollector::new();
+        metrics.update_hardware(HardwareSnapshot {
+            temperatures: vec![TemperatureReading { sensor: "acpi\"tz\"".into(), celsius: 41.0, critical_c: None }],
+            fans: vec![FanReading { fan: "it87/fan\\1".into(), rpm: 1200 }],
+            ..Default::default()
+        });
+        let output = metrics.prometheus();
+        assert!(output.contains("hw_temperature_celsius{sensor=\"acpi\\\"tz\\\"\"} 41\n"));
+        assert!(output.contains("hw_fan_rpm{fan=\"it87/fan\\\\1\"} 1200\n"));
+    }
+}
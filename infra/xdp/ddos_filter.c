@@ -2,7 +2,11 @@
  * XDP DDoS Filter - First Line Defense
  *
  * Compiles with: clang -O2 -g -target bpf -c ddos_filter.c -o ddos_filter.o
- * Load with: ip link set dev eth0 xdp obj ddos_filter.o sec xdp_ddos
+ * Load with: bpftool prog load ddos_filter.o /sys/fs/bpf/xdp_ddos type xdp
+ *            ip link set dev eth0 xdp pinned /sys/fs/bpf/xdp_ddos
+ *
+ * Maps are pinned by name under /sys/fs/bpf and driven by the sase-ddos
+ * control plane (crates/sase-ddos/src/xdp).
  */
 
 #include <bpf/bpf_endian.h>
@@ -88,6 +92,8 @@ struct xdp_stats {
   __u64 blocklist_hits;
   __u64 rate_limit_hits;
   __u64 syn_verified;
+  __u64 syn_cookies_sent;
+  __u64 syn_unverified_acks;
 };
 
 struct {
@@ -98,25 +104,556 @@ struct {
   __uint(pinning, LIBBPF_PIN_BY_NAME);
 } xdp_stats SEC(".maps");
 
-/* SYN Cookie Secrets */
+/* === SYN Cookies === */
+
+/*
+ * Destinations under SYN cookie protection. SYNs to these are answered
+ * from XDP with a cookie SYN-ACK. What happens to a client that echoes a
+ * valid cookie depends on the mode:
+ *
+ *   RESET   - our own SipHash cookie; the client is verified and reset so
+ *             its retry reaches the server directly.
+ *   HANDOFF - a kernel cookie (bpf_tcp_raw_gen_syncookie_ipv4); the ACK is
+ *             passed to the host stack, which builds the connection from
+ *             the cookie without a reconnect. Needs the server on this
+ *             host and net.ipv4.tcp_syncookies=2. SYNs without room for an
+ *             MSS option fall back to RESET cookies.
+ *
+ * With a SYN threshold set, protection is adaptive: SYNs pass untouched
+ * until the destination's SYN rate crosses it.
+ */
+#define SYN_MODE_RESET 0
+#define SYN_MODE_HANDOFF 1
+
+struct syn_protection {
+  __u64 enabled_at;        /* Enable timestamp (unix seconds) */
+  __u32 mode;              /* SYN_MODE_* */
+  __u32 syn_threshold_pps; /* 0 = cookies always on */
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_HASH);
+  __uint(max_entries, 4096);
+  __type(key, __u32); /* IPv4 destination */
+  __type(value, struct syn_protection);
+  __uint(pinning, LIBBPF_PIN_BY_NAME);
+} syn_protect SEC(".maps");
+
+/* Per-destination SYN rate for adaptive protection */
+struct syn_rate {
+  __u64 window_start;
+  __u64 syns;      /* SYNs in the current one-second window */
+  __u64 last_syns; /* SYNs in the previous window */
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_HASH);
+  __uint(max_entries, 4096);
+  __type(key, __u32);
+  __type(value, struct syn_rate);
+  __uint(pinning, LIBBPF_PIN_BY_NAME);
+} syn_rate SEC(".maps");
+
+/* Per-destination SYN cookie counters (per-CPU) */
+struct syn_cookie_stats {
+  __u64 cookies_sent;
+  __u64 cookies_validated;
+  __u64 unverified_acks;
+  __u64 verified_passed;
+  __u64 handoffs;    /* Validated ACKs passed to the host stack */
+  __u64 syns_passed; /* SYNs below the adaptive threshold */
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_PERCPU_HASH);
+  __uint(max_entries, 4096);
+  __type(key, __u32);
+  __type(value, struct syn_cookie_stats);
+  __uint(pinning, LIBBPF_PIN_BY_NAME);
+} syn_cookie_stats SEC(".maps");
+
+/*
+ * Cookie secrets, slotted by generation parity. The control plane writes
+ * the next generation into the idle slot before bumping syn_config, so
+ * cookies minted under the previous secret stay valid for one rotation.
+ */
+struct syn_secret {
+  __u64 k0;
+  __u64 k1;
+  __u32 generation;
+  __u32 pad;
+};
+
 struct {
   __uint(type, BPF_MAP_TYPE_ARRAY);
   __uint(max_entries, 2);
   __type(key, __u32);
-  __type(value, __u64);
+  __type(value, struct syn_secret);
+  __uint(pinning, LIBBPF_PIN_BY_NAME);
 } syn_secrets SEC(".maps");
 
+struct syn_config {
+  __u32 generation;        /* Current secret generation */
+  __u32 verified_ttl_secs; /* How long a verified source skips cookies */
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_ARRAY);
+  __uint(max_entries, 1);
+  __type(key, __u32);
+  __type(value, struct syn_config);
+  __uint(pinning, LIBBPF_PIN_BY_NAME);
+} syn_config SEC(".maps");
+
+/* Sources that completed a cookie handshake */
+struct syn_verified_key {
+  __u32 saddr;
+  __u32 daddr;
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_LRU_HASH);
+  __uint(max_entries, 1000000);
+  __type(key, struct syn_verified_key);
+  __type(value, __u64); /* Verification timestamp */
+  __uint(pinning, LIBBPF_PIN_BY_NAME);
+} syn_verified SEC(".maps");
+
+/*
+ * Cookie layout (32 bits):
+ *   [31]    secret generation parity
+ *   [30:26] time slot, ~68s granularity (ktime >> 36)
+ *   [25:0]  SipHash-2-4 of the 4-tuple and time slot
+ */
+#define COOKIE_GEN_SHIFT 31
+#define COOKIE_SLOT_SHIFT 26
+#define COOKIE_SLOT_MASK 0x1F
+#define COOKIE_HASH_MASK 0x3FFFFFF
+#define COOKIE_SLOT_NS_SHIFT 36
+#define COOKIE_MAX_AGE 1 /* Slots a cookie stays valid after minting */
+
+#define ROTL64(x, b) (((x) << (b)) | ((x) >> (64 - (b))))
+#define SIPROUND                                                               \
+  do {                                                                         \
+    v0 += v1;                                                                  \
+    v1 = ROTL64(v1, 13);                                                       \
+    v1 ^= v0;                                                                  \
+    v0 = ROTL64(v0, 32);                                                       \
+    v2 += v3;                                                                  \
+    v3 = ROTL64(v3, 16);                                                       \
+    v3 ^= v2;                                                                  \
+    v0 += v3;                                                                  \
+    v3 = ROTL64(v3, 21);                                                       \
+    v3 ^= v0;                                                                  \
+    v2 += v1;                                                                  \
+    v1 = ROTL64(v1, 17);                                                       \
+    v1 ^= v2;                                                                  \
+    v2 = ROTL64(v2, 32);                                                       \
+  } while (0)
+
 /* === Helper Functions === */
 
-static __always_inline __u32 get_syn_cookie(__u32 saddr, __u32 daddr,
-                                            __u16 sport, __u16 dport) {
+static __always_inline __u32 syn_cookie_hash(const struct syn_secret *secret,
+                                             __u32 saddr, __u32 daddr,
+                                             __u16 sport, __u16 dport,
+                                             __u32 slot) {
+  __u64 v0 = 0x736f6d6570736575ULL ^ secret->k0;
+  __u64 v1 = 0x646f72616e646f6dULL ^ secret->k1;
+  __u64 v2 = 0x6c7967656e657261ULL ^ secret->k0;
+  __u64 v3 = 0x7465646279746573ULL ^ secret->k1;
+
+  __u64 m0 = ((__u64)saddr << 32) | daddr;
+  __u64 m1 = ((__u64)sport << 48) | ((__u64)dport << 32) | slot;
+  __u64 b = 16ULL << 56;
+
+  v3 ^= m0;
+  SIPROUND;
+  SIPROUND;
+  v0 ^= m0;
+
+  v3 ^= m1;
+  SIPROUND;
+  SIPROUND;
+  v0 ^= m1;
+
+  v3 ^= b;
+  SIPROUND;
+  SIPROUND;
+  v0 ^= b;
+
+  v2 ^= 0xff;
+  SIPROUND;
+  SIPROUND;
+  SIPROUND;
+  SIPROUND;
+
+  return (__u32)(v0 ^ v1 ^ v2 ^ v3);
+}
+
+static __always_inline __u32 syn_cookie_slot(void) {
+  return (__u32)(bpf_ktime_get_ns() >> COOKIE_SLOT_NS_SHIFT) & COOKIE_SLOT_MASK;
+}
+
+/* Mint a cookie under the current secret generation. Returns 0 if the
+ * control plane has not installed secrets yet. */
+static __always_inline int get_syn_cookie(__u32 saddr, __u32 daddr,
+                                          __u16 sport, __u16 dport,
+                                          __u32 *cookie) {
   __u32 key = 0;
-  __u64 *secret = bpf_map_lookup_elem(&syn_secrets, &key);
-  if (!secret)
+  struct syn_config *cfg = bpf_map_lookup_elem(&syn_config, &key);
+  if (!cfg)
+    return 0;
+
+  key = cfg->generation & 1;
+  struct syn_secret *secret = bpf_map_lookup_elem(&syn_secrets, &key);
+  if (!secret || secret->generation != cfg->generation ||
+      (secret->k0 == 0 && secret->k1 == 0))
     return 0;
 
-  /* Simple hash - real implementation would use siphash */
-  return (saddr ^ daddr ^ sport ^ dport ^ (*secret)) & 0xFFFFFFFF;
+  __u32 slot = syn_cookie_slot();
+  __u32 hash = syn_cookie_hash(secret, saddr, daddr, sport, dport, slot);
+
+  *cookie = (key << COOKIE_GEN_SHIFT) | (slot << COOKIE_SLOT_SHIFT) |
+            (hash & COOKIE_HASH_MASK);
+  return 1;
+}
+
+/* Validate a cookie against the secret slot its parity bit names. */
+static __always_inline int check_syn_cookie(__u32 cookie, __u32 saddr,
+                                            __u32 daddr, __u16 sport,
+                                            __u16 dport) {
+  __u32 key = cookie >> COOKIE_GEN_SHIFT;
+  struct syn_secret *secret = bpf_map_lookup_elem(&syn_secrets, &key);
+  if (!secret || (secret->k0 == 0 && secret->k1 == 0))
+    return 0;
+
+  __u32 slot = (cookie >> COOKIE_SLOT_SHIFT) & COOKIE_SLOT_MASK;
+  __u32 age = (syn_cookie_slot() - slot) & COOKIE_SLOT_MASK;
+  if (age > COOKIE_MAX_AGE)
+    return 0;
+
+  __u32 hash = syn_cookie_hash(secret, saddr, daddr, sport, dport, slot);
+  return (hash & COOKIE_HASH_MASK) == (cookie & COOKIE_HASH_MASK);
+}
+
+static __always_inline __u16 csum_fold(__u64 sum) {
+  sum = (sum & 0xffff) + (sum >> 16);
+  sum = (sum & 0xffff) + (sum >> 16);
+  sum = (sum & 0xffff) + (sum >> 16);
+  return ~sum;
+}
+
+static __always_inline __u16 ipv4_checksum(struct iphdr *ip, void *data_end) {
+  __u16 *p = (__u16 *)ip;
+  __u64 sum = 0;
+
+#pragma unroll
+  for (int i = 0; i < (int)(sizeof(*ip) / 2); i++) {
+    if ((void *)(p + i + 1) > data_end)
+      break;
+    sum += p[i];
+  }
+  return csum_fold(sum);
+}
+
+static __always_inline __u16 tcp_checksum(struct iphdr *ip, struct tcphdr *tcp,
+                                          __u32 tcp_len, void *data_end) {
+  __u16 *p = (__u16 *)tcp;
+  __u64 sum = 0;
+
+  /* Pseudo-header */
+  sum += (ip->saddr >> 16) + (ip->saddr & 0xffff);
+  sum += (ip->daddr >> 16) + (ip->daddr & 0xffff);
+  sum += bpf_htons(IPPROTO_TCP);
+  sum += bpf_htons(tcp_len);
+
+  /* Header only - the reply carries no payload */
+#pragma unroll
+  for (int i = 0; i < 30; i++) {
+    if (i * 2 >= tcp_len)
+      break;
+    if ((void *)(p + i + 1) > data_end)
+      break;
+    sum += p[i];
+  }
+  return csum_fold(sum);
+}
+
+/*
+ * Turn the received segment into a reply in place: swap L2-L4 addressing,
+ * blank any TCP options with NOPs and drop the payload from the IP length.
+ * A non-zero mss is advertised in the first option slot.
+ */
+static __always_inline int reflect_tcp(struct xdp_md *ctx, struct ethhdr *eth,
+                                       struct iphdr *ip, struct tcphdr *tcp,
+                                       __u32 tcp_len, __u16 mss,
+                                       void *data_end) {
+  __u8 mac[ETH_ALEN];
+  __builtin_memcpy(mac, eth->h_source, ETH_ALEN);
+  __builtin_memcpy(eth->h_source, eth->h_dest, ETH_ALEN);
+  __builtin_memcpy(eth->h_dest, mac, ETH_ALEN);
+
+  __u32 addr = ip->saddr;
+  ip->saddr = ip->daddr;
+  ip->daddr = addr;
+
+  __u16 port = tcp->source;
+  tcp->source = tcp->dest;
+  tcp->dest = port;
+
+  __u8 *opt = (__u8 *)(tcp + 1);
+#pragma unroll
+  for (int i = 0; i < 40; i++) {
+    if (sizeof(*tcp) + i >= tcp_len)
+      break;
+    if ((void *)(opt + i + 1) > data_end)
+      return -1;
+    opt[i] = 1; /* TCPOPT_NOP */
+  }
+
+  if (mss && tcp_len >= sizeof(*tcp) + 4) {
+    if ((void *)(opt + 4) > data_end)
+      return -1;
+    opt[0] = 2; /* TCPOPT_MSS */
+    opt[1] = 4;
+    opt[2] = mss >> 8;
+    opt[3] = mss & 0xff;
+  }
+
+  ip->tot_len = bpf_htons(sizeof(*ip) + tcp_len);
+  ip->id = 0;
+  ip->frag_off = bpf_htons(0x4000); /* DF */
+  ip->ttl = 64;
+  ip->check = 0;
+  ip->check = ipv4_checksum(ip, data_end);
+
+  tcp->urg_ptr = 0;
+  tcp->check = 0;
+  tcp->check = tcp_checksum(ip, tcp, tcp_len, data_end);
+
+  /* Trim any payload so the reply is header-only on the wire */
+  int excess = (long)data_end - ((long)tcp + tcp_len);
+  if (excess > 0 && bpf_xdp_adjust_tail(ctx, -excess))
+    return -1;
+
+  return 0;
+}
+
+static __always_inline struct syn_cookie_stats *
+syn_cookie_counters(__u32 daddr) {
+  struct syn_cookie_stats *counters =
+      bpf_map_lookup_elem(&syn_cookie_stats, &daddr);
+  if (counters)
+    return counters;
+
+  struct syn_cookie_stats zero = {};
+  bpf_map_update_elem(&syn_cookie_stats, &daddr, &zero, BPF_NOEXIST);
+  return bpf_map_lookup_elem(&syn_cookie_stats, &daddr);
+}
+
+/*
+ * Count a SYN towards its destination's rate and report whether the
+ * destination is flooded: this or the previous second crossed the
+ * threshold. Looking back one window keeps cookies from toggling at every
+ * window boundary mid-flood. Updates race across CPUs; the rate only needs
+ * to be roughly right.
+ */
+static __always_inline int syn_flood_active(__u32 daddr,
+                                            const struct syn_protection *p) {
+  if (!p->syn_threshold_pps)
+    return 1;
+
+  __u64 now = bpf_ktime_get_ns();
+  struct syn_rate *rate = bpf_map_lookup_elem(&syn_rate, &daddr);
+  if (!rate) {
+    struct syn_rate fresh = {.window_start = now, .syns = 1};
+    bpf_map_update_elem(&syn_rate, &daddr, &fresh, BPF_NOEXIST);
+    return 0;
+  }
+
+  if (now - rate->window_start >= 1000000000ULL) {
+    /* An idle gap of more than a window means no recent flood */
+    rate->last_syns =
+        now - rate->window_start < 2000000000ULL ? rate->syns : 0;
+    rate->syns = 0;
+    rate->window_start = now;
+  }
+  __sync_fetch_and_add(&rate->syns, 1);
+
+  return rate->syns > p->syn_threshold_pps ||
+         rate->last_syns > p->syn_threshold_pps;
+}
+
+/*
+ * Answer a SYN with a kernel cookie the host stack can later accept.
+ * Returns 0 when the SYN has no room to advertise the cookie's MSS.
+ */
+static __always_inline int handoff_syn_ack(struct xdp_md *ctx,
+                                           struct ethhdr *eth,
+                                           struct iphdr *ip,
+                                           struct tcphdr *tcp, __u32 tcp_len,
+                                           void *data_end) {
+  if (tcp_len < sizeof(*tcp) + 4 || (void *)tcp + tcp_len > data_end)
+    return 0;
+
+  /* Lower 32 bits: cookie, next 16: the MSS it encodes */
+  __s64 value = bpf_tcp_raw_gen_syncookie_ipv4(ip, tcp, tcp_len);
+  if (value < 0)
+    return 0;
+  __u32 cookie = (__u32)value;
+  __u16 mss = (__u16)(value >> 32);
+
+  __u32 client_seq = bpf_ntohl(tcp->seq);
+  tcp->seq = bpf_htonl(cookie);
+  tcp->ack_seq = bpf_htonl(client_seq + 1);
+  tcp->fin = 0;
+  tcp->rst = 0;
+  tcp->psh = 0;
+  tcp->urg = 0;
+  tcp->ece = 0;
+  tcp->cwr = 0;
+  tcp->syn = 1;
+  tcp->ack = 1;
+  tcp->window = bpf_htons(65535);
+
+  if (reflect_tcp(ctx, eth, ip, tcp, tcp_len, mss, data_end))
+    return -1;
+  return 1;
+}
+
+/*
+ * Stateless SYN cookie handling for a protected destination. Returns an
+ * XDP action; XDP_PASS lets the packet continue through the filter.
+ */
+static __always_inline int syn_cookie_filter(struct xdp_md *ctx,
+                                             struct ethhdr *eth,
+                                             struct iphdr *ip,
+                                             struct tcphdr *tcp,
+                                             void *data_end,
+                                             const struct syn_protection *p,
+                                             struct xdp_stats *stats) {
+  /* IP options would shift the TCP header out of the fixed layout */
+  if (ip->ihl != 5)
+    return XDP_PASS;
+
+  __u32 tcp_len = tcp->doff * 4;
+  if (tcp_len < sizeof(*tcp) || tcp_len > 60)
+    return XDP_PASS;
+
+  __u32 saddr = ip->saddr;
+  __u32 daddr = ip->daddr;
+  struct syn_cookie_stats *counters = syn_cookie_counters(daddr);
+
+  /* Verified sources bypass cookies until their TTL lapses */
+  struct syn_verified_key vkey = {.saddr = saddr, .daddr = daddr};
+  __u64 *verified_at = bpf_map_lookup_elem(&syn_verified, &vkey);
+  if (verified_at) {
+    __u32 key = 0;
+    struct syn_config *cfg = bpf_map_lookup_elem(&syn_config, &key);
+    __u64 ttl_ns = cfg ? (__u64)cfg->verified_ttl_secs * 1000000000ULL : 0;
+    if (bpf_ktime_get_ns() - *verified_at < ttl_ns) {
+      if (counters)
+        counters->verified_passed++;
+      return XDP_PASS;
+    }
+  }
+
+  /* SYN - answer with a cookie SYN-ACK, never reaches the server */
+  if (tcp->syn && !tcp->ack) {
+    if (!syn_flood_active(daddr, p)) {
+      if (counters)
+        counters->syns_passed++;
+      return XDP_PASS;
+    }
+
+    if (p->mode == SYN_MODE_HANDOFF) {
+      int sent = handoff_syn_ack(ctx, eth, ip, tcp, tcp_len, data_end);
+      if (sent < 0)
+        return XDP_DROP;
+      if (sent) {
+        stats->syn_cookies_sent++;
+        if (counters)
+          counters->cookies_sent++;
+        return XDP_TX;
+      }
+    }
+
+    __u32 cookie;
+    if (!get_syn_cookie(saddr, daddr, tcp->source, tcp->dest, &cookie))
+      return XDP_PASS; /* No secrets installed - fail open */
+
+    __u32 client_seq = bpf_ntohl(tcp->seq);
+    tcp->seq = bpf_htonl(cookie);
+    tcp->ack_seq = bpf_htonl(client_seq + 1);
+    tcp->fin = 0;
+    tcp->rst = 0;
+    tcp->psh = 0;
+    tcp->urg = 0;
+    tcp->ece = 0;
+    tcp->cwr = 0;
+    tcp->syn = 1;
+    tcp->ack = 1;
+    tcp->window = bpf_htons(65535);
+
+    if (reflect_tcp(ctx, eth, ip, tcp, tcp_len, 0, data_end))
+      return XDP_DROP;
+
+    stats->syn_cookies_sent++;
+    if (counters)
+      counters->cookies_sent++;
+    return XDP_TX;
+  }
+
+  /*
+   * Bare ACK - may complete a cookie handshake. ACKs that don't carry our
+   * cookie pass untouched so flows established before protection survive.
+   */
+  if (tcp->ack && !tcp->syn && !tcp->rst && !tcp->fin) {
+    /* Kernel cookie - the host stack completes the connection */
+    if (p->mode == SYN_MODE_HANDOFF &&
+        bpf_tcp_raw_check_syncookie_ipv4(ip, tcp) == 0) {
+      __u64 now = bpf_ktime_get_ns();
+      bpf_map_update_elem(&syn_verified, &vkey, &now, BPF_ANY);
+      stats->syn_verified++;
+      if (counters) {
+        counters->cookies_validated++;
+        counters->handoffs++;
+      }
+      return XDP_PASS;
+    }
+
+    __u32 cookie = bpf_ntohl(tcp->ack_seq) - 1;
+    if (!check_syn_cookie(cookie, saddr, daddr, tcp->source, tcp->dest)) {
+      stats->syn_unverified_acks++;
+      if (counters)
+        counters->unverified_acks++;
+      return XDP_PASS;
+    }
+
+    __u64 now = bpf_ktime_get_ns();
+    bpf_map_update_elem(&syn_verified, &vkey, &now, BPF_ANY);
+
+    /* The server never saw the SYN - reset so the client reconnects */
+    tcp->seq = tcp->ack_seq;
+    tcp->ack_seq = 0;
+    tcp->ack = 0;
+    tcp->psh = 0;
+    tcp->urg = 0;
+    tcp->ece = 0;
+    tcp->cwr = 0;
+    tcp->rst = 1;
+    tcp->window = 0;
+
+    if (reflect_tcp(ctx, eth, ip, tcp, tcp_len, 0, data_end))
+      return XDP_DROP;
+
+    stats->syn_verified++;
+    if (counters)
+      counters->cookies_validated++;
+    return XDP_TX;
+  }
+
+  return XDP_PASS;
 }
 
 static __always_inline int check_blocklist(__u32 saddr) {
@@ -224,10 +761,14 @@ int xdp_ddos_filter(struct xdp_md *ctx) {
       if ((void *)(tcp + 1) > data_end)
         return XDP_PASS;
 
-      /* SYN packet - verify or cookie */
-      if (tcp->syn && !tcp->ack) {
-        /* For high-rate SYN floods, could implement SYN cookies here */
-        /* Currently just pass - VPP handles SYN cookies */
+      /* SYN cookies for protected destinations */
+      struct syn_protection *protection =
+          bpf_map_lookup_elem(&syn_protect, &ip->daddr);
+      if (protection) {
+        int action =
+            syn_cookie_filter(ctx, eth, ip, tcp, data_end, protection, stats);
+        if (action != XDP_PASS)
+          return action;
       }
     }
 
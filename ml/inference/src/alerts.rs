@@ -28,6 +28,19 @@ impl AlertManager {
         category: ThreatCategory,
         confidence: f64,
         explanation: String,
+    ) -> ThreatAlert {
+        self.create_alert_with_evidence(severity, category, confidence, explanation, Vec::new(), HashMap::new())
+    }
+
+    /// Create alert with entities and supporting evidence data
+    pub fn create_alert_with_evidence(
+        &self,
+        severity: Severity,
+        category: ThreatCategory,
+        confidence: f64,
+        explanation: String,
+        entities: Vec<Entity>,
+        data: HashMap<String, String>,
     ) -> ThreatAlert {
         let alert = ThreatAlert {
             alert_id: Uuid::new_v4(),
@@ -36,11 +49,11 @@ impl AlertManager {
             category,
             confidence,
             source: AlertSource::MlModel,
-            entities: Vec::new(),
+            entities,
             evidence: vec![Evidence {
                 evidence_type: "ml_prediction".into(),
                 description: explanation,
-                data: HashMap::new(),
+                data,
             }],
             recommended_action: self.get_recommended_action(&category, &severity),
             mitre_attack: self.map_mitre(&category),
@@ -118,6 +131,11 @@ impl AlertManager {
                 technique: "T1041".into(),
                 name: "Exfiltration Over C2 Channel".into(),
             }),
+            ThreatCategory::DataExfiltration => Some(MitreMapping {
+                tactic: "Exfiltration".into(),
+                technique: "T1048".into(),
+                name: "Exfiltration Over Alternative Protocol".into(),
+            }),
             _ => None,
         }
     }
@@ -0,0 +1,349 @@
+//! Encrypted Traffic Analysis (ETA)
+//!
+//! Flags malware C2 and exfiltration over TLS without decryption, using the
+//! per-flow records exported by the dataplane ETA extractor: sequence of
+//! packet lengths and times (SPLT) plus handshake metadata (JA4, SNI,
+//! certificate validity). The record types are shared with the dataplane
+//! through `sase_common::eta`.
+
+use crate::OstieError;
+use crate::features::calculate_entropy;
+use std::collections::HashSet;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+pub use sase_common::eta::{CertificateInfo, Direction, EtaRecord, PacketSample};
+
+/// ETA features for ML
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtaFeatures {
+    /// Mean outbound payload length (SPLT)
+    pub mean_len_out: f64,
+    /// Mean inbound payload length (SPLT)
+    pub mean_len_in: f64,
+    /// Std-dev of outbound payload lengths (SPLT)
+    pub std_len_out: f64,
+    /// Mean inter-arrival time (ms)
+    pub mean_iat_ms: f64,
+    /// Inter-arrival time coefficient of variation (low = periodic)
+    pub iat_cv: f64,
+    /// SPLT packets observed
+    pub splt_packets: f64,
+    /// Outbound share of payload bytes (0-1)
+    pub outbound_ratio: f64,
+    /// Outbound payload bytes
+    pub bytes_out: f64,
+    /// Flow duration (seconds)
+    pub duration: f64,
+    /// Certificate age at flow start (days, -1 if unknown)
+    pub cert_age_days: f64,
+    /// Certificate validity period (days, -1 if unknown)
+    pub cert_validity_days: f64,
+    /// Self-signed certificate (0/1)
+    pub self_signed: f64,
+    /// SNI character entropy
+    pub sni_entropy: f64,
+    /// No SNI offered (0/1)
+    pub sni_missing: f64,
+    /// No ALPN offered (0/1)
+    pub alpn_missing: f64,
+}
+
+impl EtaFeatures {
+    /// Feature names, in vector order
+    pub const NAMES: [&'static str; 15] = [
+        "mean_len_out",
+        "mean_len_in",
+        "std_len_out",
+        "mean_iat_ms",
+        "iat_cv",
+        "splt_packets",
+        "outbound_ratio",
+        "bytes_out",
+        "duration",
+        "cert_age_days",
+        "cert_validity_days",
+        "self_signed",
+        "sni_entropy",
+        "sni_missing",
+        "alpn_missing",
+    ];
+
+    /// Extract features from a flow record
+    pub fn from_flow(flow: &EtaRecord) -> Self {
+        let lens = |dir: Direction| -> Vec<f64> {
+            flow.splt.iter()
+                .filter(|p| p.direction == dir)
+                .map(|p| p.payload_len as f64)
+                .collect()
+        };
+        let out_lens = lens(Direction::Outbound);
+        let in_lens = lens(Direction::Inbound);
+
+        // First sample has no predecessor
+        let iats: Vec<f64> = flow.splt.iter()
+            .skip(1)
+            .map(|p| p.iat_us as f64 / 1000.0)
+            .collect();
+        let mean_iat = mean(&iats);
+        let iat_cv = if mean_iat > 0.0 { std_dev(&iats) / mean_iat } else { 0.0 };
+
+        let total = flow.bytes_out + flow.bytes_in;
+        let start_secs = (flow.start_us / 1_000_000) as i64;
+        let (cert_age_days, cert_validity_days, self_signed) = match &flow.certificate {
+            Some(c) => (
+                (start_secs - c.not_before) as f64 / 86_400.0,
+                (c.not_after - c.not_before) as f64 / 86_400.0,
+                if c.self_signed == Some(true) { 1.0 } else { 0.0 },
+            ),
+            None => (-1.0, -1.0, 0.0),
+        };
+
+        Self {
+            mean_len_out: mean(&out_lens),
+            mean_len_in: mean(&in_lens),
+            std_len_out: std_dev(&out_lens),
+            mean_iat_ms: mean_iat,
+            iat_cv,
+            splt_packets: flow.splt.len() as f64,
+            outbound_ratio: if total > 0 { flow.bytes_out as f64 / total as f64 } else { 0.0 },
+            bytes_out: flow.bytes_out as f64,
+            duration: flow.duration_us as f64 / 1_000_000.0,
+            cert_age_days,
+            cert_validity_days,
+            self_signed,
+            sni_entropy: flow.sni.as_deref().map(calculate_entropy).unwrap_or(0.0),
+            sni_missing: if flow.sni.is_none() { 1.0 } else { 0.0 },
+            alpn_missing: if flow.alpn.is_none() { 1.0 } else { 0.0 },
+        }
+    }
+
+    /// Convert to feature vector
+    pub fn to_vector(&self) -> Vec<f64> {
+        vec![
+            self.mean_len_out,
+            self.mean_len_in,
+            self.std_len_out,
+            self.mean_iat_ms,
+            self.iat_cv,
+            self.splt_packets,
+            self.outbound_ratio,
+            self.bytes_out,
+            self.duration,
+            self.cert_age_days,
+            self.cert_validity_days,
+            self.self_signed,
+            self.sni_entropy,
+            self.sni_missing,
+            self.alpn_missing,
+        ]
+    }
+}
+
+/// Encrypted Traffic Detector (SPLT + handshake metadata)
+pub struct EtaDetector {
+    model_loaded: Arc<RwLock<bool>>,
+    known_bad_ja4: Arc<RwLock<HashSet<String>>>,
+    threshold: Arc<RwLock<f64>>,
+}
+
+impl EtaDetector {
+    /// Create detector
+    pub fn new() -> Self {
+        Self {
+            model_loaded: Arc::new(RwLock::new(false)),
+            known_bad_ja4: Arc::new(RwLock::new(HashSet::new())),
+            threshold: Arc::new(RwLock::new(0.7)),
+        }
+    }
+
+    /// Add a known-malicious JA4 fingerprint (e.g. from threat intel)
+    pub fn add_bad_ja4(&self, ja4: impl Into<String>) {
+        self.known_bad_ja4.write().insert(ja4.into());
+    }
+
+    /// Predict C2 / exfiltration
+    pub fn predict(&self, flow: &EtaRecord) -> EtaPrediction {
+        let features = EtaFeatures::from_flow(flow);
+        let ja4_match = flow.ja4.as_ref()
+            .is_some_and(|j| self.known_bad_ja4.read().contains(j));
+
+        let c2 = self.c2_contributions(&features, ja4_match);
+        let exfil = self.exfil_contributions(&features, flow.complete);
+        let c2_score = total(&c2);
+        let exfil_score = total(&exfil);
+
+        let threshold = *self.threshold.read();
+        let (threat_type, confidence, mut contributing) = if c2_score >= exfil_score && c2_score > threshold {
+            (EtaThreatType::MalwareC2, c2_score, c2)
+        } else if exfil_score > threshold {
+            (EtaThreatType::Exfiltration, exfil_score, exfil)
+        } else {
+            (EtaThreatType::None, c2_score.max(exfil_score), Vec::new())
+        };
+        contributing.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        EtaPrediction {
+            c2_score,
+            exfil_score,
+            is_threat: threat_type != EtaThreatType::None,
+            threat_type,
+            confidence,
+            ja4_match,
+            explanation: self.explain(flow, threat_type, &contributing),
+            contributing_features: contributing,
+            features,
+        }
+    }
+
+    fn c2_contributions(&self, f: &EtaFeatures, ja4_match: bool) -> Vec<(String, f64)> {
+        let mut c = Vec::new();
+
+        if ja4_match {
+            c.push(("ja4".into(), 0.6));
+        }
+        // Beaconing: regular timing with enough samples
+        if f.splt_packets >= 6.0 && f.iat_cv < 0.2 && f.mean_iat_ms > 0.0 {
+            c.push(("iat_cv".into(), 0.25));
+        }
+        // Uniform small outbound packets (check-ins)
+        if f.mean_len_out > 0.0 && f.mean_len_out < 300.0 && f.std_len_out < 20.0 {
+            c.push(("std_len_out".into(), 0.15));
+        }
+        if f.self_signed > 0.0 {
+            c.push(("self_signed".into(), 0.2));
+        }
+        if (0.0..30.0).contains(&f.cert_age_days) {
+            c.push(("cert_age_days".into(), 0.15));
+        }
+        if f.sni_missing > 0.0 {
+            c.push(("sni_missing".into(), 0.1));
+        } else if f.sni_entropy > 3.8 {
+            c.push(("sni_entropy".into(), 0.15));
+        }
+        if f.alpn_missing > 0.0 {
+            c.push(("alpn_missing".into(), 0.05));
+        }
+
+        c
+    }
+
+    fn exfil_contributions(&self, f: &EtaFeatures, complete: bool) -> Vec<(String, f64)> {
+        let mut c = Vec::new();
+
+        // Byte totals are only meaningful once the flow has ended
+        if complete && f.outbound_ratio > 0.8 {
+            if f.bytes_out > 10_000_000.0 {
+                c.push(("bytes_out".into(), 0.4));
+            }
+            if f.bytes_out > 100_000_000.0 {
+                c.push(("outbound_ratio".into(), 0.2));
+            }
+        }
+        // Full-size outbound segments dominating the early exchange
+        if f.mean_len_out > 1000.0 && f.mean_len_out > 4.0 * f.mean_len_in {
+            c.push(("mean_len_out".into(), 0.2));
+        }
+        if f.self_signed > 0.0 {
+            c.push(("self_signed".into(), 0.1));
+        }
+        if (0.0..30.0).contains(&f.cert_age_days) {
+            c.push(("cert_age_days".into(), 0.1));
+        }
+        if f.sni_missing > 0.0 {
+            c.push(("sni_missing".into(), 0.05));
+        }
+
+        c
+    }
+
+    fn explain(&self, flow: &EtaRecord, threat_type: EtaThreatType, contributing: &[(String, f64)]) -> String {
+        let features: Vec<&str> = contributing.iter().map(|(n, _)| n.as_str()).collect();
+        let server = flow.sni.as_deref().unwrap_or("<no SNI>");
+        match threat_type {
+            EtaThreatType::MalwareC2 => format!(
+                "Encrypted C2-like traffic to {} (JA4 {}). Indicators: {}",
+                server,
+                flow.ja4.as_deref().unwrap_or("-"),
+                features.join(", ")
+            ),
+            EtaThreatType::Exfiltration => format!(
+                "Possible exfiltration over TLS to {}: {:.1}MB outbound. Indicators: {}",
+                server,
+                flow.bytes_out as f64 / 1_000_000.0,
+                features.join(", ")
+            ),
+            EtaThreatType::None => "No encrypted-traffic indicators".into(),
+        }
+    }
+
+    /// Load a tuned model (JSON [`EtaModel`]): its threshold replaces the
+    /// current one and its JA4 fingerprints are added to the known-bad set
+    pub fn load(&self, path: &str) -> Result<(), OstieError> {
+        let data = std::fs::read(path)
+            .map_err(|e| OstieError::Model(format!("{}: {}", path, e)))?;
+        let model: EtaModel = serde_json::from_slice(&data)
+            .map_err(|e| OstieError::Model(format!("{}: {}", path, e)))?;
+        if !(model.threshold > 0.0 && model.threshold <= 1.0) {
+            return Err(OstieError::Model(format!("{}: threshold {} outside (0, 1]", path, model.threshold)));
+        }
+
+        *self.threshold.write() = model.threshold;
+        self.known_bad_ja4.write().extend(model.known_bad_ja4);
+        *self.model_loaded.write() = true;
+        Ok(())
+    }
+}
+
+/// Tuned ETA model parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtaModel {
+    /// Score above which a flow is reported
+    pub threshold: f64,
+    /// JA4 fingerprints of known malware families
+    #[serde(default)]
+    pub known_bad_ja4: Vec<String>,
+}
+
+impl Default for EtaDetector {
+    fn default() -> Self { Self::new() }
+}
+
+/// ETA prediction result
+#[derive(Debug, Clone)]
+pub struct EtaPrediction {
+    pub c2_score: f64,
+    pub exfil_score: f64,
+    pub is_threat: bool,
+    pub threat_type: EtaThreatType,
+    pub confidence: f64,
+    pub ja4_match: bool,
+    /// Features behind the verdict, strongest first
+    pub contributing_features: Vec<(String, f64)>,
+    pub features: EtaFeatures,
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtaThreatType {
+    MalwareC2,
+    Exfiltration,
+    None,
+}
+
+fn total(contributions: &[(String, f64)]) -> f64 {
+    contributions.iter().map(|(_, w)| w).sum::<f64>().min(1.0)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() { return 0.0; }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 { return 0.0; }
+    let m = mean(values);
+    let var = values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / values.len() as f64;
+    var.sqrt()
+}
@@ -151,7 +151,7 @@ pub struct TlsFingerprint {
 }
 
 // Helper functions
-fn calculate_entropy(s: &str) -> f64 {
+pub(crate) fn calculate_entropy(s: &str) -> f64 {
     let mut freq = [0u32; 256];
     for b in s.bytes() {
         freq[b as usize] += 1;
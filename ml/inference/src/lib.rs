@@ -42,7 +42,10 @@ pub mod alerts;
 pub mod intel;
 pub mod hunting;
 pub mod feedback;
+pub mod eta;
 
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use thiserror::Error;
@@ -51,6 +54,7 @@ pub use features::*;
 pub use models::*;
 pub use inference::InferenceEngine;
 pub use alerts::{ThreatAlert, AlertManager};
+pub use eta::{EtaDetector, EtaRecord};
 
 /// OSTIE error types
 #[derive(Debug, Error)]
@@ -73,6 +77,8 @@ pub struct ThreatEngine {
     pub uba_detector: Arc<models::UbaDetector>,
     /// Malware traffic detector
     pub malware_detector: Arc<models::MalwareDetector>,
+    /// Encrypted traffic analysis detector
+    pub eta_detector: Arc<eta::EtaDetector>,
     /// Alert manager
     pub alerts: Arc<AlertManager>,
     /// Inference engine
@@ -87,6 +93,7 @@ impl ThreatEngine {
             network_detector: Arc::new(models::NetworkAnomalyDetector::new()),
             uba_detector: Arc::new(models::UbaDetector::new()),
             malware_detector: Arc::new(models::MalwareDetector::new()),
+            eta_detector: Arc::new(eta::EtaDetector::new()),
             alerts: Arc::new(AlertManager::new()),
             inference: Arc::new(InferenceEngine::new()),
         }
@@ -141,6 +148,44 @@ impl ThreatEngine {
         }
     }
 
+    /// Analyze encrypted flow (ETA record from the dataplane)
+    pub async fn analyze_encrypted_flow(&self, flow: &EtaRecord) -> Option<ThreatAlert> {
+        let result = self.eta_detector.predict(flow);
+
+        let (severity, category) = match result.threat_type {
+            eta::EtaThreatType::MalwareC2 if result.ja4_match => (alerts::Severity::Critical, alerts::ThreatCategory::MalwareC2),
+            eta::EtaThreatType::MalwareC2 => (alerts::Severity::High, alerts::ThreatCategory::MalwareC2),
+            eta::EtaThreatType::Exfiltration => (alerts::Severity::High, alerts::ThreatCategory::DataExfiltration),
+            eta::EtaThreatType::None => return None,
+        };
+
+        let mut entities = vec![
+            alerts::Entity { entity_type: alerts::EntityType::Ip, value: Ipv4Addr::from(flow.src_ip).to_string() },
+            alerts::Entity { entity_type: alerts::EntityType::Ip, value: Ipv4Addr::from(flow.dst_ip).to_string() },
+        ];
+        if let Some(sni) = &flow.sni {
+            entities.push(alerts::Entity { entity_type: alerts::EntityType::Domain, value: sni.clone() });
+        }
+
+        let mut data: HashMap<String, String> = result.contributing_features.iter()
+            .map(|(name, weight)| (format!("feature.{}", name), format!("{:.2}", weight)))
+            .collect();
+        if let Some(ja4) = &flow.ja4 {
+            data.insert("ja4".into(), ja4.clone());
+        }
+        data.insert("c2_score".into(), format!("{:.2}", result.c2_score));
+        data.insert("exfil_score".into(), format!("{:.2}", result.exfil_score));
+
+        Some(self.alerts.create_alert_with_evidence(
+            severity,
+            category,
+            result.confidence,
+            result.explanation,
+            entities,
+            data,
+        ))
+    }
+
     /// Load models
     pub async fn load_models(&self, path: &str) -> Result<(), OstieError> {
         tracing::info!("Loading models from {}", path);
@@ -148,6 +193,7 @@ impl ThreatEngine {
         self.network_detector.load(&format!("{}/network", path))?;
         self.uba_detector.load(&format!("{}/uba", path))?;
         self.malware_detector.load(&format!("{}/malware", path))?;
+        self.eta_detector.load(&format!("{}/eta.json", path))?;
         Ok(())
     }
 }
@@ -1,6 +1,11 @@
 //! API Analytics
 //!
-//! Collect and analyze API usage metrics.
+//! Collect and analyze API usage metrics. Per-consumer monthly usage is
+//! aggregated separately from sampled request logs and pushed to billing.
+
+mod usage;
+
+pub use usage::{BucketHistogram, ConsumerUsage, UsageAggregator, UsageFlushReport, UsageSink, LATENCY_BUCKETS_MS};
 
 use crate::AnalyticsConfig;
 use chrono::{DateTime, Utc};
@@ -13,6 +18,7 @@ pub struct AnalyticsCollector {
     config: AnalyticsConfig,
     requests: parking_lot::RwLock<Vec<RequestLog>>,
     metrics: ApiMetrics,
+    usage: UsageAggregator,
 }
 
 /// Request log entry
@@ -32,6 +38,9 @@ pub struct RequestLog {
     pub client_ip: String,
     pub user_agent: Option<String>,
     pub error: Option<String>,
+    /// Upstream target (`host:port`) that served the request
+    #[serde(default)]
+    pub upstream: Option<String>,
 }
 
 /// API metrics
@@ -160,15 +169,24 @@ impl AnalyticsCollector {
             config,
             requests: parking_lot::RwLock::new(Vec::new()),
             metrics: ApiMetrics::new(),
+            usage: UsageAggregator::new(),
         }
     }
     
+    /// Per-consumer usage for billing
+    pub fn usage(&self) -> &UsageAggregator {
+        &self.usage
+    }
+    
     /// Record a request
     pub fn record(&self, log: RequestLog) {
         if !self.config.enabled {
             return;
         }
         
+        // Billing counts every request, not just the sampled ones
+        self.usage.record(&log);
+        
         // Apply sampling
         if self.config.sample_rate < 1.0 {
             let sample: f32 = rand::random();
@@ -231,6 +249,16 @@ impl AnalyticsCollector {
             .collect()
     }
     
+    /// Requests and 5xx responses served by an upstream target since a time
+    pub fn upstream_errors(&self, target: &str, since: DateTime<Utc>) -> (u64, u64) {
+        self.requests.read()
+            .iter()
+            .filter(|r| r.timestamp >= since && r.upstream.as_deref() == Some(target))
+            .fold((0, 0), |(total, errors), r| {
+                (total + 1, errors + u64::from(r.status_code >= 500))
+            })
+    }
+    
     /// Get error requests
     pub fn get_errors(&self) -> Vec<RequestLog> {
         self.requests.read()
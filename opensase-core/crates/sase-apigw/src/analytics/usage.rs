@@ -0,0 +1,327 @@
+//! Per-Consumer API Usage
+//!
+//! Monthly request counts, bandwidth and latency histograms per consumer,
+//! pushed to billing for usage-based API pricing. Each flush sends the
+//! growth of every month's totals since the previous flush as `UsageEvent`s
+//! for the consumer's tenant.
+//!
+//! Events are keyed by the cumulative total they bring the month up to, so
+//! a batch that is resent after an ambiguous failure is not billed twice.
+//! Failed batches are kept in a bounded [`UsageOutbox`] and retried ahead of
+//! new usage.
+
+use super::RequestLog;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use parking_lot::Mutex;
+use sase_billing::{UsageEvent, UsageMetric, UsageOutbox};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub use sase_billing::UsageSink;
+
+const BYTES_PER_GB: f64 = 1_073_741_824.0;
+
+/// Upper bounds (ms) of the latency buckets; the last bucket is unbounded
+pub const LATENCY_BUCKETS_MS: [u32; 12] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000];
+
+/// Fixed-bucket latency histogram
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BucketHistogram {
+    /// Counts per [`LATENCY_BUCKETS_MS`] bound, plus one overflow bucket
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum_ms: u64,
+}
+
+impl BucketHistogram {
+    pub fn record(&mut self, latency_ms: u32) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; LATENCY_BUCKETS_MS.len() + 1];
+        }
+        let bucket = LATENCY_BUCKETS_MS.iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += latency_ms as u64;
+    }
+
+    /// Upper bound of the bucket holding the quantile (0.0-1.0); `None` when
+    /// empty or in the overflow bucket
+    pub fn quantile(&self, q: f64) -> Option<u32> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count as f64 * q.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return LATENCY_BUCKETS_MS.get(i).copied();
+            }
+        }
+        None
+    }
+
+    pub fn avg(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum_ms as f64 / self.count as f64
+        }
+    }
+}
+
+/// A consumer's usage in one calendar month
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConsumerUsage {
+    pub consumer_id: String,
+    /// First day of the month
+    pub month: NaiveDate,
+    pub requests: u64,
+    pub errors: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub latency: BucketHistogram,
+    pub last_request: DateTime<Utc>,
+}
+
+/// Totals already turned into usage events
+#[derive(Clone, Debug, Default)]
+struct Pushed {
+    requests: u64,
+    bytes_in: u64,
+    bytes_out: u64,
+}
+
+#[derive(Clone, Debug)]
+struct MonthEntry {
+    usage: ConsumerUsage,
+    pushed: Pushed,
+}
+
+/// Result of one flush
+#[derive(Debug, Clone, Default)]
+pub struct UsageFlushReport {
+    /// Events delivered to the sink
+    pub sent: usize,
+    /// Events still queued after this flush
+    pub backlog: usize,
+    /// Events dropped because the backlog was full
+    pub dropped: usize,
+    /// Consumers with usage but no billing tenant
+    pub unbound: Vec<String>,
+    /// Sink error, when delivery failed
+    pub error: Option<String>,
+}
+
+/// Per-consumer monthly usage accounting
+pub struct UsageAggregator {
+    months: Mutex<HashMap<(String, NaiveDate), MonthEntry>>,
+    /// Consumer id to billing tenant
+    tenants: Mutex<HashMap<String, Uuid>>,
+    outbox: UsageOutbox,
+}
+
+impl UsageAggregator {
+    pub fn new() -> Self {
+        Self {
+            months: Mutex::new(HashMap::new()),
+            tenants: Mutex::new(HashMap::new()),
+            outbox: UsageOutbox::new("API", 100_000),
+        }
+    }
+
+    /// Events kept while billing is unavailable; oldest dropped beyond this
+    pub fn with_max_backlog(mut self, max_backlog: usize) -> Self {
+        self.outbox = UsageOutbox::new("API", max_backlog);
+        self
+    }
+
+    /// Bill a consumer's usage to a tenant
+    pub fn bind_consumer(&self, consumer_id: &str, tenant_id: Uuid) {
+        self.tenants.lock().insert(consumer_id.to_string(), tenant_id);
+    }
+
+    pub fn unbind_consumer(&self, consumer_id: &str) {
+        self.tenants.lock().remove(consumer_id);
+    }
+
+    pub fn tenant_of(&self, consumer_id: &str) -> Option<Uuid> {
+        self.tenants.lock().get(consumer_id).copied()
+    }
+
+    /// Account a request; anonymous requests are not billed
+    pub fn record(&self, log: &RequestLog) {
+        let Some(consumer) = log.consumer_id.as_ref() else {
+            return;
+        };
+        let month = month_of(log.timestamp);
+
+        let mut months = self.months.lock();
+        let entry = months.entry((consumer.clone(), month)).or_insert_with(|| MonthEntry {
+            usage: ConsumerUsage {
+                consumer_id: consumer.clone(),
+                month,
+                requests: 0,
+                errors: 0,
+                bytes_in: 0,
+                bytes_out: 0,
+                latency: BucketHistogram::default(),
+                last_request: log.timestamp,
+            },
+            pushed: Pushed::default(),
+        });
+
+        let usage = &mut entry.usage;
+        usage.requests += 1;
+        usage.errors += u64::from(log.status_code >= 400);
+        usage.bytes_in += log.request_size;
+        usage.bytes_out += log.response_size;
+        usage.latency.record(log.latency_ms);
+        usage.last_request = usage.last_request.max(log.timestamp);
+    }
+
+    /// A consumer's usage for the month containing `month`
+    pub fn consumer_usage(&self, consumer_id: &str, month: NaiveDate) -> Option<ConsumerUsage> {
+        self.months.lock()
+            .get(&(consumer_id.to_string(), month_of_date(month)))
+            .map(|e| e.usage.clone())
+    }
+
+    /// All consumers' usage for the month containing `month`, busiest first
+    pub fn monthly_usage(&self, month: NaiveDate) -> Vec<ConsumerUsage> {
+        let month = month_of_date(month);
+        let mut usage: Vec<_> = self.months.lock()
+            .values()
+            .filter(|e| e.usage.month == month)
+            .map(|e| e.usage.clone())
+            .collect();
+        usage.sort_by_key(|u| std::cmp::Reverse(u.requests));
+        usage
+    }
+
+    /// Events waiting for delivery
+    pub fn backlog_len(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Push usage accrued since the last flush, with any backlog. Months
+    /// before the current one are dropped once fully pushed.
+    pub async fn flush(&self, sink: &dyn UsageSink, now: DateTime<Utc>) -> UsageFlushReport {
+        let mut unbound = Vec::new();
+        let delivery = self.outbox.flush(sink, || {
+            let (events, consumers) = self.drain(now);
+            unbound = consumers;
+            events
+        }).await;
+
+        if !unbound.is_empty() {
+            tracing::debug!("API usage held for {} consumers without a billing tenant", unbound.len());
+        }
+        UsageFlushReport {
+            sent: delivery.sent,
+            backlog: delivery.backlog,
+            dropped: delivery.dropped,
+            unbound,
+            error: delivery.error,
+        }
+    }
+
+    /// Turn unpushed growth into events. Usage of unbound consumers stays
+    /// unpushed until they are bound.
+    fn drain(&self, now: DateTime<Utc>) -> (Vec<UsageEvent>, Vec<String>) {
+        let tenants = self.tenants.lock().clone();
+        let current = month_of(now);
+        let mut events = Vec::new();
+        let mut unbound = Vec::new();
+        let mut months = self.months.lock();
+
+        for ((consumer, month), entry) in months.iter_mut() {
+            let usage = &entry.usage;
+            let pushed = &mut entry.pushed;
+            if usage.requests == pushed.requests
+                && usage.bytes_in == pushed.bytes_in
+                && usage.bytes_out == pushed.bytes_out
+            {
+                continue;
+            }
+            let Some(tenant_id) = tenants.get(consumer).copied() else {
+                if !unbound.contains(consumer) {
+                    unbound.push(consumer.clone());
+                }
+                continue;
+            };
+
+            let month_key = month.format("%Y-%m").to_string();
+            let mut dimensions = HashMap::from([
+                ("service".to_string(), "apigw".to_string()),
+                ("consumer".to_string(), consumer.clone()),
+                ("month".to_string(), month_key.clone()),
+            ]);
+            let mut push = |metric: UsageMetric, name: &str, value: f64, total: u64, dimensions: HashMap<String, String>| {
+                events.push(UsageEvent {
+                    tenant_id,
+                    // Attribute to the month the usage happened in
+                    timestamp: usage.last_request,
+                    metric,
+                    value,
+                    dimensions,
+                    idempotency_key: Some(format!(
+                        "apigw:{}:{}:{}:{}:{}",
+                        tenant_id, consumer, month_key, name, total,
+                    )),
+                });
+            };
+
+            if usage.bytes_in > pushed.bytes_in {
+                let bytes = usage.bytes_in - pushed.bytes_in;
+                push(UsageMetric::BandwidthIngressGB, "in", bytes as f64 / BYTES_PER_GB, usage.bytes_in, dimensions.clone());
+            }
+            if usage.bytes_out > pushed.bytes_out {
+                let bytes = usage.bytes_out - pushed.bytes_out;
+                push(UsageMetric::BandwidthEgressGB, "out", bytes as f64 / BYTES_PER_GB, usage.bytes_out, dimensions.clone());
+            }
+            if usage.requests > pushed.requests {
+                // Latency so far this month, for usage reports
+                for (label, q) in [("latency_p50_ms", 0.5), ("latency_p95_ms", 0.95), ("latency_p99_ms", 0.99)] {
+                    let value = usage.latency.quantile(q)
+                        .map(|ms| ms.to_string())
+                        .unwrap_or_else(|| format!(">{}", LATENCY_BUCKETS_MS[LATENCY_BUCKETS_MS.len() - 1]));
+                    dimensions.insert(label.to_string(), value);
+                }
+                dimensions.insert("errors".to_string(), usage.errors.to_string());
+                push(UsageMetric::APIRequests, "requests", (usage.requests - pushed.requests) as f64, usage.requests, dimensions);
+            }
+
+            *pushed = Pushed {
+                requests: usage.requests,
+                bytes_in: usage.bytes_in,
+                bytes_out: usage.bytes_out,
+            };
+        }
+
+        months.retain(|(_, month), entry| {
+            *month >= current
+                || entry.usage.requests > entry.pushed.requests
+                || entry.usage.bytes_in > entry.pushed.bytes_in
+                || entry.usage.bytes_out > entry.pushed.bytes_out
+        });
+        (events, unbound)
+    }
+}
+
+impl Default for UsageAggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn month_of(timestamp: DateTime<Utc>) -> NaiveDate {
+    month_of_date(timestamp.date_naive())
+}
+
+fn month_of_date(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
@@ -0,0 +1,240 @@
+//! Weighted Routing and Canary Releases
+//!
+//! A route is split across backends by pointing it at a dedicated service
+//! whose host is a Kong upstream, with one weighted target per backend:
+//!
+//! ```text
+//!   route ──> service "<route>-split" ──> upstream "<route>-split"
+//!                                           ├─ primary:8080  weight 95
+//!                                           └─ canary:8080   weight 5
+//! ```
+//!
+//! The route's original service is left untouched, so rolling back is a
+//! matter of pointing the route at it again. Canary health comes from
+//! analytics: once enough requests have reached the canary target, a 5xx
+//! rate above the policy threshold triggers an automatic rollback.
+
+use crate::GatewayError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Tag on every entity created for a traffic split
+pub const SPLIT_TAG: &str = "opensase-split";
+
+// =============================================================================
+// Traffic Splits
+// =============================================================================
+
+/// Backend receiving a share of a route's traffic
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeightedBackend {
+    /// `host:port`
+    pub target: String,
+    /// Relative weight, 0-1000 (Kong's range); 0 receives no traffic
+    pub weight: u32,
+    /// Kong target ID once created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<String>,
+}
+
+impl WeightedBackend {
+    pub fn new(target: &str, weight: u32) -> Self {
+        Self {
+            target: target.to_string(),
+            weight,
+            target_id: None,
+        }
+    }
+}
+
+/// Route whose traffic is spread over several backends
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrafficSplit {
+    pub route_id: String,
+    /// Service the route used before the split
+    pub original_service_id: String,
+    pub split_service_id: String,
+    pub upstream_id: String,
+    pub backends: Vec<WeightedBackend>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TrafficSplit {
+    pub fn backend(&self, target: &str) -> Option<&WeightedBackend> {
+        self.backends.iter().find(|b| b.target == target)
+    }
+}
+
+/// Check a set of backends before splitting traffic over them
+pub fn validate_backends(backends: &[WeightedBackend]) -> Result<(), GatewayError> {
+    if backends.len() < 2 {
+        return Err(GatewayError::ConfigError("A traffic split needs at least two backends".to_string()));
+    }
+    for backend in backends {
+        if !backend.target.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()) {
+            return Err(GatewayError::ConfigError(format!("Backend {} is not host:port", backend.target)));
+        }
+        if backend.weight > 1000 {
+            return Err(GatewayError::ConfigError(format!("Backend {} weight exceeds 1000", backend.target)));
+        }
+    }
+    if backends.iter().all(|b| b.weight == 0) {
+        return Err(GatewayError::ConfigError("All backend weights are zero".to_string()));
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Canary Releases
+// =============================================================================
+
+/// When a canary is considered unhealthy
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CanaryPolicy {
+    /// 5xx responses as a percentage of canary requests
+    pub max_error_rate: f64,
+    /// Canary requests needed before the error rate is trusted
+    pub min_requests: u64,
+    /// Look-back window for the error rate
+    pub window_secs: u64,
+    /// Roll back without operator action when the threshold is exceeded
+    pub auto_rollback: bool,
+}
+
+impl Default for CanaryPolicy {
+    fn default() -> Self {
+        Self {
+            max_error_rate: 5.0,
+            min_requests: 50,
+            window_secs: 300,
+            auto_rollback: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CanaryState {
+    Running,
+    Promoted,
+    RolledBack,
+}
+
+/// Canary error rate over the policy window
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CanaryHealth {
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+}
+
+impl CanaryHealth {
+    pub fn new(requests: u64, errors: u64) -> Self {
+        let error_rate = if requests == 0 {
+            0.0
+        } else {
+            errors as f64 / requests as f64 * 100.0
+        };
+        Self { requests, errors, error_rate }
+    }
+
+    /// Reason to roll back, if the policy is breached
+    pub fn breach(&self, policy: &CanaryPolicy) -> Option<String> {
+        (self.requests >= policy.min_requests && self.error_rate > policy.max_error_rate).then(|| format!(
+            "canary 5xx rate {:.1}% over {} requests exceeds {:.1}%",
+            self.error_rate, self.requests, policy.max_error_rate
+        ))
+    }
+}
+
+/// Canary release on one route
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CanaryDeployment {
+    pub route_id: String,
+    pub split: TrafficSplit,
+    pub primary_target: String,
+    pub canary_target: String,
+    pub policy: CanaryPolicy,
+    pub state: CanaryState,
+    pub health: CanaryHealth,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub rollback_reason: Option<String>,
+}
+
+impl CanaryDeployment {
+    /// Current canary weight out of the split total, as a percentage
+    pub fn canary_percentage(&self) -> f64 {
+        let total: u32 = self.split.backends.iter().map(|b| b.weight).sum();
+        let canary = self.split.backend(&self.canary_target).map_or(0, |b| b.weight);
+        if total == 0 {
+            0.0
+        } else {
+            canary as f64 / total as f64 * 100.0
+        }
+    }
+}
+
+/// Active splits and canary history
+pub struct CanaryManager {
+    splits: parking_lot::RwLock<HashMap<String, TrafficSplit>>,
+    canaries: parking_lot::RwLock<HashMap<String, CanaryDeployment>>,
+}
+
+impl CanaryManager {
+    pub fn new() -> Self {
+        Self {
+            splits: parking_lot::RwLock::new(HashMap::new()),
+            canaries: parking_lot::RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn split(&self, route_id: &str) -> Option<TrafficSplit> {
+        self.splits.read().get(route_id).cloned()
+    }
+
+    pub fn splits(&self) -> Vec<TrafficSplit> {
+        self.splits.read().values().cloned().collect()
+    }
+
+    pub fn insert_split(&self, split: TrafficSplit) {
+        self.splits.write().insert(split.route_id.clone(), split);
+    }
+
+    pub fn remove_split(&self, route_id: &str) -> Option<TrafficSplit> {
+        self.splits.write().remove(route_id)
+    }
+
+    /// Latest canary for a route, finished or not
+    pub fn canary(&self, route_id: &str) -> Option<CanaryDeployment> {
+        self.canaries.read().get(route_id).cloned()
+    }
+
+    pub fn running(&self) -> Vec<CanaryDeployment> {
+        self.canaries.read()
+            .values()
+            .filter(|c| c.state == CanaryState::Running)
+            .cloned()
+            .collect()
+    }
+
+    pub fn upsert_canary(&self, canary: CanaryDeployment) {
+        self.canaries.write().insert(canary.route_id.clone(), canary);
+    }
+
+    /// Keep the canary's copy of the split in step with the split itself
+    pub fn update_split(&self, split: TrafficSplit) {
+        if let Some(canary) = self.canaries.write().get_mut(&split.route_id) {
+            if canary.state == CanaryState::Running {
+                canary.split = split.clone();
+            }
+        }
+        self.insert_split(split);
+    }
+}
+
+impl Default for CanaryManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,658 @@
+//! GraphQL Protection
+//!
+//! Query depth and complexity limits, introspection blocking, persisted
+//! query allowlists and per-operation rate limits for GraphQL upstreams.
+//!
+//! A [`GraphqlPolicy`] renders to a Kong `pre-function` plugin that runs the
+//! checks in the access phase, and backs [`GraphqlGuard`] for in-process
+//! enforcement. Both use the same query analysis:
+//!
+//! - depth is the deepest selection-set nesting
+//! - complexity is the number of field selections
+//! - fragment spreads are not expanded
+
+use crate::ratelimit::SlidingWindow;
+use crate::{GatewayError, Plugin};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Header carrying the checked operation names to the upstream
+pub const OPERATION_HEADER: &str = "X-GraphQL-Operation";
+
+// =============================================================================
+// Policy
+// =============================================================================
+
+/// GraphQL protection settings for one upstream
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GraphqlPolicy {
+    pub max_depth: Option<u32>,
+    /// Maximum field selections per query
+    pub max_complexity: Option<u32>,
+    /// Reject `__schema` and `__type` queries
+    pub block_introspection: bool,
+    /// Accept array bodies carrying several operations
+    pub allow_batching: bool,
+    /// Only serve queries in the allowlist
+    pub allowlist_only: bool,
+    /// Persisted queries by hex SHA-256 of the query text
+    pub allowlist: BTreeMap<String, PersistedQuery>,
+    /// Limits by operation name
+    pub operation_limits: BTreeMap<String, OperationLimit>,
+    /// Limit for operations without their own
+    pub default_operation_limit: Option<OperationLimit>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PersistedQuery {
+    pub operation_name: Option<String>,
+    pub query: String,
+}
+
+/// Requests per client per window
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct OperationLimit {
+    pub limit: u64,
+    pub window_secs: u64,
+}
+
+impl Default for GraphqlPolicy {
+    fn default() -> Self {
+        Self {
+            max_depth: Some(10),
+            max_complexity: Some(500),
+            block_introspection: true,
+            allow_batching: false,
+            allowlist_only: false,
+            allowlist: BTreeMap::new(),
+            operation_limits: BTreeMap::new(),
+            default_operation_limit: None,
+        }
+    }
+}
+
+impl GraphqlPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_depth(mut self, depth: u32) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn with_max_complexity(mut self, complexity: u32) -> Self {
+        self.max_complexity = Some(complexity);
+        self
+    }
+
+    pub fn allow_introspection(mut self) -> Self {
+        self.block_introspection = false;
+        self
+    }
+
+    pub fn allow_batching(mut self) -> Self {
+        self.allow_batching = true;
+        self
+    }
+
+    /// Reject any query that is not persisted
+    pub fn allowlist_only(mut self) -> Self {
+        self.allowlist_only = true;
+        self
+    }
+
+    /// Add a query to the persisted allowlist
+    pub fn with_persisted_query(mut self, query: &str) -> Self {
+        self.allowlist.insert(query_hash(query), PersistedQuery {
+            operation_name: operation_name(query),
+            query: query.to_string(),
+        });
+        self
+    }
+
+    pub fn with_operation_limit(mut self, operation: &str, limit: u64, window_secs: u64) -> Self {
+        self.operation_limits.insert(operation.to_string(), OperationLimit { limit, window_secs });
+        self
+    }
+
+    pub fn with_default_operation_limit(mut self, limit: u64, window_secs: u64) -> Self {
+        self.default_operation_limit = Some(OperationLimit { limit, window_secs });
+        self
+    }
+
+    /// Kong `pre-function` plugin enforcing this policy on a service
+    pub fn to_plugin(&self, service_id: &str) -> Result<Plugin, GatewayError> {
+        let plugin = Plugin::new("pre-function", serde_json::json!({
+            "access": [self.to_lua(service_id)?]
+        })).for_service(service_id);
+        Ok(plugin)
+    }
+
+    /// Access-phase Lua; `scope` namespaces the rate limit counters
+    pub fn to_lua(&self, scope: &str) -> Result<String, GatewayError> {
+        if let Some((name, _)) = self.operation_limits.iter().find(|(_, l)| l.window_secs == 0) {
+            return Err(GatewayError::ConfigError(format!("Operation limit for {} has a zero window", name)));
+        }
+        if self.default_operation_limit.is_some_and(|l| l.window_secs == 0) {
+            return Err(GatewayError::ConfigError("Default operation limit has a zero window".to_string()));
+        }
+
+        // Absent settings are left out: cjson decodes null as a truthy value
+        let mut config = serde_json::json!({
+            "scope": scope,
+            "block_introspection": self.block_introspection,
+            "allow_batching": self.allow_batching,
+            "allowlist_only": self.allowlist_only,
+            "allowlist": self.allowlist.iter()
+                .map(|(hash, persisted)| (hash.clone(), serde_json::json!(persisted.query)))
+                .collect::<serde_json::Map<_, _>>(),
+            "operation_limits": self.operation_limits.iter()
+                .map(|(op, limit)| (op.clone(), serde_json::json!(limit)))
+                .collect::<serde_json::Map<_, _>>(),
+        });
+        if let Some(depth) = self.max_depth {
+            config["max_depth"] = serde_json::json!(depth);
+        }
+        if let Some(complexity) = self.max_complexity {
+            config["max_complexity"] = serde_json::json!(complexity);
+        }
+        if let Some(limit) = self.default_operation_limit {
+            config["default_operation_limit"] = serde_json::json!(limit);
+        }
+
+        let json = serde_json::to_string(&config)
+            .map_err(|e| GatewayError::ConfigError(e.to_string()))?;
+        Ok(LUA_ACCESS.replace("__CONFIG__", &lua_long_string(&json)))
+    }
+}
+
+// =============================================================================
+// Query Analysis
+// =============================================================================
+
+/// Shape of a GraphQL query
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryAnalysis {
+    pub depth: u32,
+    pub complexity: u32,
+    pub introspection: bool,
+}
+
+/// Measure a query without a full parse; mirrors the generated Lua
+pub fn analyze(query: &str) -> QueryAnalysis {
+    let bytes = query.as_bytes();
+    let mut analysis = QueryAnalysis::default();
+    let (mut depth, mut parens, mut skip) = (0i32, 0i32, 0u8);
+    let mut prev = b' ';
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' if bytes[i..].starts_with(b"\"\"\"") => {
+                i = query[i + 3..].find("\"\"\"").map(|e| i + 3 + e + 2).unwrap_or(bytes.len());
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'(' => parens += 1,
+            b')' => parens -= 1,
+            b'{' if parens == 0 => {
+                depth += 1;
+                analysis.depth = analysis.depth.max(depth.max(0) as u32);
+                skip = 0;
+            }
+            b'}' if parens == 0 => depth -= 1,
+            b'.' => {
+                skip = 1;
+                prev = c;
+            }
+            b'@' | b'$' => prev = c,
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i + 1 < bytes.len() && (bytes[i + 1].is_ascii_alphanumeric() || bytes[i + 1] == b'_') {
+                    i += 1;
+                }
+                let name = &query[start..=i];
+                let alias = query[i + 1..].trim_start().starts_with(':');
+
+                if prev == b'@' || prev == b'$' {
+                    // Directive or variable
+                } else if skip > 0 {
+                    // Fragment spread, or `on Type` of an inline fragment
+                    skip = if name == "on" { 1 } else { 0 };
+                } else if depth > 0 && parens == 0 && !alias {
+                    analysis.complexity += 1;
+                    if name == "__schema" || name == "__type" {
+                        analysis.introspection = true;
+                    }
+                }
+                prev = b'n';
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    analysis
+}
+
+/// Hex SHA-256 of a query, as used for persisted query lookups
+pub fn query_hash(query: &str) -> String {
+    hex::encode(Sha256::digest(query.as_bytes()))
+}
+
+/// Name of the first operation, e.g. `GetUser` in `query GetUser { .. }`
+pub fn operation_name(query: &str) -> Option<String> {
+    let mut words = query.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).filter(|w| !w.is_empty());
+    match words.next()? {
+        "query" | "mutation" | "subscription" => words.next()
+            .filter(|w| !w.starts_with(|c: char| c.is_ascii_digit()))
+            .map(String::from),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// In-process Guard
+// =============================================================================
+
+/// GraphQL request body
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GraphqlRequest {
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default, rename = "operationName")]
+    pub operation_name: Option<String>,
+    #[serde(default)]
+    pub variables: Option<serde_json::Value>,
+    #[serde(default)]
+    pub extensions: Option<serde_json::Value>,
+}
+
+/// Checked operation
+#[derive(Clone, Debug)]
+pub struct GraphqlOperation {
+    pub operation_name: String,
+    /// Query text, resolved from the allowlist for hash-only requests
+    pub query: String,
+    pub analysis: QueryAnalysis,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum GraphqlViolation {
+    #[error("Invalid GraphQL request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Batched GraphQL requests are not allowed")]
+    BatchingDisabled,
+
+    #[error("PersistedQueryNotFound")]
+    PersistedQueryNotFound,
+
+    #[error("Query is not in the persisted query allowlist")]
+    NotAllowlisted,
+
+    #[error("Introspection is disabled")]
+    Introspection,
+
+    #[error("Query depth {depth} exceeds limit of {limit}")]
+    DepthExceeded { depth: u32, limit: u32 },
+
+    #[error("Query complexity {complexity} exceeds limit of {limit}")]
+    ComplexityExceeded { complexity: u32, limit: u32 },
+
+    #[error("Rate limit exceeded for operation {0}")]
+    RateLimited(String),
+}
+
+impl GraphqlViolation {
+    /// HTTP status the gateway answers with
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::NotAllowlisted | Self::Introspection => 403,
+            Self::RateLimited(_) => 429,
+            _ => 400,
+        }
+    }
+}
+
+/// Enforces a [`GraphqlPolicy`] in process
+pub struct GraphqlGuard {
+    policy: GraphqlPolicy,
+    /// Windows per `operation:client`
+    windows: DashMap<String, SlidingWindow>,
+}
+
+impl GraphqlGuard {
+    pub fn new(policy: GraphqlPolicy) -> Self {
+        Self {
+            policy,
+            windows: DashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> &GraphqlPolicy {
+        &self.policy
+    }
+
+    /// Check a raw request body (single request or batch) for a client
+    pub fn check_body(&self, body: &[u8], client: &str) -> Result<Vec<GraphqlOperation>, GraphqlViolation> {
+        let value: serde_json::Value = serde_json::from_slice(body)
+            .map_err(|e| GraphqlViolation::InvalidRequest(e.to_string()))?;
+        let requests: Vec<GraphqlRequest> = match value {
+            serde_json::Value::Array(items) => {
+                if !self.policy.allow_batching {
+                    return Err(GraphqlViolation::BatchingDisabled);
+                }
+                items.into_iter()
+                    .map(serde_json::from_value)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| GraphqlViolation::InvalidRequest(e.to_string()))?
+            }
+            other => vec![serde_json::from_value(other)
+                .map_err(|e| GraphqlViolation::InvalidRequest(e.to_string()))?],
+        };
+
+        requests.iter().map(|r| self.check(r, client)).collect()
+    }
+
+    /// Check one request for a client (consumer ID or IP)
+    pub fn check(&self, request: &GraphqlRequest, client: &str) -> Result<GraphqlOperation, GraphqlViolation> {
+        let query = match &request.query {
+            Some(query) if !query.is_empty() => query.clone(),
+            _ => {
+                let hash = request.extensions.as_ref()
+                    .and_then(|e| e["persistedQuery"]["sha256Hash"].as_str())
+                    .ok_or(GraphqlViolation::PersistedQueryNotFound)?;
+                self.policy.allowlist.get(hash)
+                    .map(|p| p.query.clone())
+                    .ok_or(GraphqlViolation::PersistedQueryNotFound)?
+            }
+        };
+
+        if self.policy.allowlist_only && !self.policy.allowlist.contains_key(&query_hash(&query)) {
+            return Err(GraphqlViolation::NotAllowlisted);
+        }
+
+        let analysis = analyze(&query);
+        if self.policy.block_introspection && analysis.introspection {
+            return Err(GraphqlViolation::Introspection);
+        }
+        if let Some(limit) = self.policy.max_depth {
+            if analysis.depth > limit {
+                return Err(GraphqlViolation::DepthExceeded { depth: analysis.depth, limit });
+            }
+        }
+        if let Some(limit) = self.policy.max_complexity {
+            if analysis.complexity > limit {
+                return Err(GraphqlViolation::ComplexityExceeded { complexity: analysis.complexity, limit });
+            }
+        }
+
+        let operation_name = request.operation_name.clone()
+            .filter(|n| !n.is_empty())
+            .or_else(|| operation_name(&query))
+            .unwrap_or_else(|| "anonymous".to_string());
+
+        let limit = self.policy.operation_limits.get(&operation_name)
+            .copied()
+            .or(self.policy.default_operation_limit);
+        if let Some(limit) = limit {
+            let acquired = self.windows
+                .entry(format!("{}:{}", operation_name, client))
+                .or_insert_with(|| SlidingWindow::new(Duration::from_secs(limit.window_secs), limit.limit))
+                .try_acquire();
+            if !acquired {
+                return Err(GraphqlViolation::RateLimited(operation_name));
+            }
+        }
+
+        Ok(GraphqlOperation { operation_name, query, analysis })
+    }
+}
+
+// =============================================================================
+// Kong Lua
+// =============================================================================
+
+/// Wrap text in a Lua long bracket string it cannot terminate early
+fn lua_long_string(text: &str) -> String {
+    let mut level = 0;
+    while text.contains(&format!("]{}]", "=".repeat(level))) {
+        level += 1;
+    }
+    let eq = "=".repeat(level);
+    format!("[{eq}[{text}]{eq}]")
+}
+
+const LUA_ACCESS: &str = r##"-- OpenSASE GraphQL protection (generated)
+local cjson = require("cjson.safe")
+local resty_sha256 = require("resty.sha256")
+local to_hex = require("resty.string").to_hex
+
+local cfg = cjson.decode(__CONFIG__)
+local counters = ngx.shared.kong_rate_limiting_counters or ngx.shared.kong
+
+local function sha256_hex(s)
+  local h = resty_sha256:new()
+  h:update(s)
+  return to_hex(h:final())
+end
+
+local function analyze(q)
+  local depth, max_depth, parens, fields, skip = 0, 0, 0, 0, 0
+  local introspection = false
+  local prev = ""
+  local i, n = 1, #q
+  while i <= n do
+    local c = q:sub(i, i)
+    if c == "#" then
+      i = q:find("\n", i, true) or n
+    elseif q:sub(i, i + 2) == '"""' then
+      i = (q:find('"""', i + 3, true) or n) + 2
+    elseif c == '"' then
+      i = i + 1
+      while i <= n do
+        local d = q:sub(i, i)
+        if d == "\\" then
+          i = i + 1
+        elseif d == '"' then
+          break
+        end
+        i = i + 1
+      end
+    elseif c == "(" then
+      parens = parens + 1
+    elseif c == ")" then
+      parens = parens - 1
+    elseif c == "{" and parens == 0 then
+      depth = depth + 1
+      if depth > max_depth then max_depth = depth end
+      skip = 0
+    elseif c == "}" and parens == 0 then
+      depth = depth - 1
+    elseif c == "." then
+      skip = 1
+      prev = c
+    elseif c == "@" or c == "$" then
+      prev = c
+    elseif c:find("[%a_]") then
+      local _, e = q:find("^[%w_]+", i)
+      local name = q:sub(i, e)
+      local alias = q:find("^%s*:", e + 1) ~= nil
+      if prev == "@" or prev == "$" then
+        -- directive or variable
+      elseif skip > 0 then
+        skip = (name == "on") and 1 or 0
+      elseif depth > 0 and parens == 0 and not alias then
+        fields = fields + 1
+        if name == "__schema" or name == "__type" then introspection = true end
+      end
+      prev = "n"
+      i = e
+    end
+    i = i + 1
+  end
+  return max_depth, fields, introspection
+end
+
+local rewritten = false
+
+local function check(req)
+  local query = req.query
+  if type(query) ~= "string" or query == "" then
+    local ext = type(req.extensions) == "table" and req.extensions.persistedQuery
+    local hash = type(ext) == "table" and ext.sha256Hash
+    query = type(hash) == "string" and cfg.allowlist[hash]
+    if not query then
+      return 400, "PersistedQueryNotFound"
+    end
+    req.query = query
+    rewritten = true
+  end
+
+  if cfg.allowlist_only and not cfg.allowlist[sha256_hex(query)] then
+    return 403, "Query is not in the persisted query allowlist"
+  end
+
+  local depth, fields, introspection = analyze(query)
+  if cfg.block_introspection and introspection then
+    return 403, "Introspection is disabled"
+  end
+  if cfg.max_depth and depth > cfg.max_depth then
+    return 400, "Query depth " .. depth .. " exceeds limit of " .. cfg.max_depth
+  end
+  if cfg.max_complexity and fields > cfg.max_complexity then
+    return 400, "Query complexity " .. fields .. " exceeds limit of " .. cfg.max_complexity
+  end
+
+  local op = req.operationName
+  if type(op) ~= "string" or op == "" then
+    local keyword, name = query:match("^%s*(%a+)%s+([%a_][%w_]*)")
+    op = (keyword == "query" or keyword == "mutation" or keyword == "subscription") and name or "anonymous"
+  end
+
+  local limit = cfg.operation_limits[op] or cfg.default_operation_limit
+  if limit and counters then
+    local consumer = kong.client.get_consumer()
+    local client = consumer and consumer.id or kong.client.get_forwarded_ip()
+    local bucket = math.floor(ngx.now() / limit.window_secs)
+    local key = "graphql:" .. cfg.scope .. ":" .. op .. ":" .. client .. ":" .. bucket
+    local count = counters:incr(key, 1, 0, limit.window_secs)
+    if count and count > limit.limit then
+      return 429, "Rate limit exceeded for operation " .. op
+    end
+  end
+  return nil, op
+end
+
+local method = kong.request.get_method()
+local body
+if method == "GET" then
+  local args = kong.request.get_query()
+  body = {
+    query = args.query,
+    operationName = args.operationName,
+    extensions = type(args.extensions) == "string" and cjson.decode(args.extensions) or nil,
+  }
+else
+  local raw = kong.request.get_raw_body()
+  body = raw and cjson.decode(raw)
+end
+if type(body) ~= "table" then
+  return kong.response.exit(400, { message = "Invalid GraphQL request" })
+end
+
+local batch = body[1] ~= nil
+if batch and not cfg.allow_batching then
+  return kong.response.exit(400, { message = "Batched GraphQL requests are not allowed" })
+end
+
+local ops = {}
+for _, req in ipairs(batch and body or { body }) do
+  if type(req) ~= "table" then
+    return kong.response.exit(400, { message = "Invalid GraphQL request" })
+  end
+  local status, result = check(req)
+  if status then
+    return kong.response.exit(status, { message = result })
+  end
+  ops[#ops + 1] = result
+end
+
+kong.service.request.set_header("X-GraphQL-Operation", table.concat(ops, ","))
+if rewritten then
+  if method == "GET" then
+    local args = kong.request.get_query()
+    args.query = body.query
+    kong.service.request.set_query(args)
+  else
+    kong.service.request.set_raw_body(cjson.encode(body))
+  end
+end
+"##;
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config JSON embedded in the script's long bracket string
+    fn embedded_config(lua: &str) -> serde_json::Value {
+        let start = lua.find("cjson.decode([").unwrap() + "cjson.decode([".len();
+        let level = lua[start..].chars().take_while(|c| *c == '=').count();
+        let close = format!("]{}])", "=".repeat(level));
+        let body_start = start + level + 1;
+        let body_end = lua[body_start..].find(&close).unwrap() + body_start;
+        serde_json::from_str(&lua[body_start..body_end]).unwrap()
+    }
+
+    #[test]
+    fn test_lua_script_emitted_whole() {
+        let policy = GraphqlPolicy::new()
+            .with_persisted_query("query Me { me { id } }")
+            .with_operation_limit("Me", 10, 60);
+        let lua = policy.to_lua("svc-1").unwrap();
+
+        assert!(lua.starts_with("-- OpenSASE GraphQL protection (generated)\n"));
+        // Comment skipping relies on the `"#"` literal surviving intact
+        assert!(lua.contains("if c == \"#\" then"));
+        assert!(lua.contains("elseif q:sub(i, i + 2) == '\"\"\"' then"));
+        assert!(lua.trim_end().ends_with("end"));
+        assert!(lua.contains("kong.service.request.set_raw_body(cjson.encode(body))"));
+        assert!(!lua.contains("__CONFIG__"));
+
+        let config = embedded_config(&lua);
+        assert_eq!(config["scope"], "svc-1");
+        assert_eq!(config["max_depth"], 10);
+        assert_eq!(config["operation_limits"]["Me"]["limit"], 10);
+        assert_eq!(config["allowlist"][query_hash("query Me { me { id } }")], "query Me { me { id } }");
+    }
+
+    #[test]
+    fn test_lua_config_cannot_close_long_string() {
+        let query = "query X { a(s: \"]]\") { b } }";
+        let lua = GraphqlPolicy::new().with_persisted_query(query).to_lua("svc").unwrap();
+
+        assert!(lua.contains("cjson.decode([=["));
+        assert_eq!(embedded_config(&lua)["allowlist"][query_hash(query)], query);
+    }
+}
@@ -0,0 +1,666 @@
+//! Declarative (DB-less) Kong configuration
+//!
+//! A DB-less Kong has a read-only Admin API: the only way to change it is to
+//! push a complete declarative config to `/config`, which reloads every
+//! worker. [`DeclarativeStore`] stages Services, Routes, Consumers and
+//! Plugins locally, renders them into decK-compatible YAML and remembers the
+//! last config Kong accepted, so a sync can diff against it and skip the
+//! reload when nothing changed.
+//!
+//! ```text
+//!  create_*/update_*/delete_*          sync()
+//!  ─────────────────────────► desired ───────► diff(applied, desired)
+//!                                                 │
+//!                              empty ◄────────────┤
+//!                           (no reload)           │ changes
+//!                                                 ▼
+//!                                   render YAML ─► POST /config ─► applied
+//! ```
+
+use crate::{Consumer, GatewayError, Plugin, Route, Service};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// decK file format emitted by [`DeclarativeConfig::to_yaml`]
+pub const FORMAT_VERSION: &str = "3.0";
+
+/// Fields Kong stamps itself; never part of the desired state
+const GENERATED_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+/// Complete desired state of a DB-less Kong, keyed by entity ID
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DeclarativeConfig {
+    pub services: BTreeMap<String, Service>,
+    pub routes: BTreeMap<String, Route>,
+    pub consumers: BTreeMap<String, Consumer>,
+    pub plugins: BTreeMap<String, Plugin>,
+}
+
+impl DeclarativeConfig {
+    /// Render as a decK-compatible declarative file
+    ///
+    /// Routes nest under their service and plugins under the single entity
+    /// they are scoped to; global plugins and plugins scoped to more than one
+    /// entity stay top-level with name references.
+    pub fn to_yaml(&self) -> Result<String, GatewayError> {
+        let mut services = Vec::new();
+        for (id, service) in &self.services {
+            let mut entry = entity_value(service);
+            let routes: Vec<Value> = self.routes.values()
+                .filter(|r| r.service.as_ref().map(|s| s.id.as_str()) == Some(id.as_str()))
+                .map(|route| {
+                    let mut route_entry = entity_value(route);
+                    route_entry.remove("service");
+                    self.nest_plugins(&mut route_entry, Scope::Route(route.id.as_deref().unwrap_or_default()));
+                    Value::Object(route_entry)
+                })
+                .collect();
+            if !routes.is_empty() {
+                entry.insert("routes".to_string(), Value::Array(routes));
+            }
+            self.nest_plugins(&mut entry, Scope::Service(id));
+            services.push(Value::Object(entry));
+        }
+
+        let routes: Vec<Value> = self.routes.values()
+            .filter(|r| r.service.is_none())
+            .map(|route| {
+                let mut entry = entity_value(route);
+                self.nest_plugins(&mut entry, Scope::Route(route.id.as_deref().unwrap_or_default()));
+                Value::Object(entry)
+            })
+            .collect();
+
+        let consumers: Vec<Value> = self.consumers.iter()
+            .map(|(id, consumer)| {
+                let mut entry = entity_value(consumer);
+                self.nest_plugins(&mut entry, Scope::Consumer(id));
+                Value::Object(entry)
+            })
+            .collect();
+
+        let plugins: Vec<Value> = self.plugins.values()
+            .filter(|p| scope_count(p) != 1)
+            .map(|plugin| {
+                let mut entry = entity_value(plugin);
+                if let Some(service) = &plugin.service {
+                    entry.insert("service".to_string(), Value::String(self.service_name(&service.id)));
+                }
+                if let Some(route) = &plugin.route {
+                    entry.insert("route".to_string(), Value::String(self.route_name(&route.id)));
+                }
+                if let Some(consumer) = &plugin.consumer {
+                    entry.insert("consumer".to_string(), Value::String(self.consumer_name(&consumer.id)));
+                }
+                Value::Object(entry)
+            })
+            .collect();
+
+        let mut file = Map::new();
+        file.insert("_format_version".to_string(), Value::String(FORMAT_VERSION.to_string()));
+        for (key, entries) in [("services", services), ("routes", routes), ("consumers", consumers), ("plugins", plugins)] {
+            if !entries.is_empty() {
+                file.insert(key.to_string(), Value::Array(entries));
+            }
+        }
+
+        serde_yaml::to_string(&Value::Object(file))
+            .map_err(|e| GatewayError::ConfigError(e.to_string()))
+    }
+
+    /// Entity-level changes needed to turn `self` into `desired`
+    pub fn diff(&self, desired: &DeclarativeConfig) -> ConfigDiff {
+        let mut changes = Vec::new();
+        diff_entities(EntityKind::Service, &self.services, &desired.services, |s| s.name.clone(), &mut changes);
+        diff_entities(EntityKind::Route, &self.routes, &desired.routes, |r| r.name.clone(), &mut changes);
+        diff_entities(EntityKind::Consumer, &self.consumers, &desired.consumers, |c| c.username.clone(), &mut changes);
+        diff_entities(EntityKind::Plugin, &self.plugins, &desired.plugins, |p| p.name.clone(), &mut changes);
+        ConfigDiff { changes }
+    }
+
+    fn nest_plugins(&self, entry: &mut Map<String, Value>, scope: Scope<'_>) {
+        let plugins: Vec<Value> = self.plugins.values()
+            .filter(|p| scope_count(p) == 1 && scope.matches(p))
+            .map(|plugin| {
+                let mut plugin_entry = entity_value(plugin);
+                for key in ["service", "route", "consumer"] {
+                    plugin_entry.remove(key);
+                }
+                Value::Object(plugin_entry)
+            })
+            .collect();
+        if !plugins.is_empty() {
+            entry.insert("plugins".to_string(), Value::Array(plugins));
+        }
+    }
+
+    fn service_name(&self, id: &str) -> String {
+        self.services.get(id).map(|s| s.name.clone()).unwrap_or_else(|| id.to_string())
+    }
+
+    fn route_name(&self, id: &str) -> String {
+        self.routes.get(id).map(|r| r.name.clone()).unwrap_or_else(|| id.to_string())
+    }
+
+    fn consumer_name(&self, id: &str) -> String {
+        self.consumers.get(id).map(|c| c.username.clone()).unwrap_or_else(|| id.to_string())
+    }
+}
+
+enum Scope<'a> {
+    Service(&'a str),
+    Route(&'a str),
+    Consumer(&'a str),
+}
+
+impl Scope<'_> {
+    fn matches(&self, plugin: &Plugin) -> bool {
+        match self {
+            Scope::Service(id) => plugin.service.as_ref().is_some_and(|s| s.id == *id),
+            Scope::Route(id) => plugin.route.as_ref().is_some_and(|r| r.id == *id),
+            Scope::Consumer(id) => plugin.consumer.as_ref().is_some_and(|c| c.id == *id),
+        }
+    }
+}
+
+fn scope_count(plugin: &Plugin) -> usize {
+    [plugin.service.is_some(), plugin.route.is_some(), plugin.consumer.is_some()]
+        .iter()
+        .filter(|s| **s)
+        .count()
+}
+
+/// Entity as a JSON object without nulls or Kong-generated timestamps; the
+/// form used both for rendering and for change detection
+pub(crate) fn entity_value<T: Serialize>(entity: &T) -> Map<String, Value> {
+    match serde_json::to_value(entity) {
+        Ok(Value::Object(map)) => map.into_iter()
+            .filter(|(k, v)| !v.is_null() && !GENERATED_FIELDS.contains(&k.as_str()))
+            .collect(),
+        _ => Map::new(),
+    }
+}
+
+fn diff_entities<T: Serialize>(
+    kind: EntityKind,
+    current: &BTreeMap<String, T>,
+    desired: &BTreeMap<String, T>,
+    name: impl Fn(&T) -> String,
+    changes: &mut Vec<EntityChange>,
+) {
+    for (id, entity) in desired {
+        let action = match current.get(id) {
+            None => ChangeAction::Create,
+            Some(existing) if entity_value(existing) != entity_value(entity) => ChangeAction::Update,
+            Some(_) => continue,
+        };
+        changes.push(EntityChange { kind, id: id.clone(), name: name(entity), action });
+    }
+    for (id, entity) in current.iter().filter(|(id, _)| !desired.contains_key(*id)) {
+        changes.push(EntityChange { kind, id: id.clone(), name: name(entity), action: ChangeAction::Delete });
+    }
+}
+
+// =============================================================================
+// Diff
+// =============================================================================
+
+/// Changes between the applied and desired configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConfigDiff {
+    pub changes: Vec<EntityChange>,
+}
+
+impl ConfigDiff {
+    /// Nothing changed; a sync would not reload Kong
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changes of one kind
+    pub fn count(&self, action: ChangeAction) -> usize {
+        self.changes.iter().filter(|c| c.action == action).count()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntityChange {
+    pub kind: EntityKind,
+    pub id: String,
+    pub name: String,
+    pub action: ChangeAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntityKind {
+    Service,
+    Route,
+    Consumer,
+    Plugin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// Result of a declarative sync
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub diff: ConfigDiff,
+    /// Config was pushed and Kong reloaded
+    pub reloaded: bool,
+    pub synced_at: chrono::DateTime<chrono::Utc>,
+}
+
+// =============================================================================
+// Store
+// =============================================================================
+
+/// Staged entities plus the last configuration Kong accepted
+#[derive(Default)]
+pub struct DeclarativeStore {
+    desired: RwLock<DeclarativeConfig>,
+    applied: RwLock<Option<DeclarativeConfig>>,
+}
+
+impl DeclarativeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot of the staged configuration
+    pub fn desired(&self) -> DeclarativeConfig {
+        self.desired.read().clone()
+    }
+
+    /// Changes a sync would push; everything counts as new before the
+    /// first sync
+    pub fn pending(&self) -> ConfigDiff {
+        let desired = self.desired.read();
+        match &*self.applied.read() {
+            Some(applied) => applied.diff(&desired),
+            None => DeclarativeConfig::default().diff(&desired),
+        }
+    }
+
+    /// Record a configuration Kong accepted
+    pub fn mark_applied(&self, config: DeclarativeConfig) {
+        *self.applied.write() = Some(config);
+    }
+
+    /// Replace the staged configuration, e.g. from a decK file in git
+    pub fn replace(&self, config: DeclarativeConfig) {
+        *self.desired.write() = config;
+    }
+
+    // -------------------------------------------------------------------------
+    // Services
+    // -------------------------------------------------------------------------
+
+    pub fn create_service(&self, mut service: Service) -> Result<Service, GatewayError> {
+        let mut desired = self.desired.write();
+        if desired.services.values().any(|s| s.name == service.name) {
+            return Err(GatewayError::ConfigError(format!("service {} already exists", service.name)));
+        }
+        let id = service.id.get_or_insert_with(new_id).clone();
+        desired.services.insert(id, service.clone());
+        Ok(service)
+    }
+
+    pub fn get_service(&self, id_or_name: &str) -> Result<Service, GatewayError> {
+        let desired = self.desired.read();
+        find(&desired.services, id_or_name, |s| &s.name)
+            .cloned()
+            .ok_or_else(|| GatewayError::ServiceNotFound(id_or_name.to_string()))
+    }
+
+    pub fn update_service(&self, id_or_name: &str, mut service: Service) -> Result<Service, GatewayError> {
+        let mut desired = self.desired.write();
+        let id = find_id(&desired.services, id_or_name, |s| &s.name)
+            .ok_or_else(|| GatewayError::ServiceNotFound(id_or_name.to_string()))?;
+        service.id = Some(id.clone());
+        desired.services.insert(id, service.clone());
+        Ok(service)
+    }
+
+    /// Delete a service and its plugins; fails while routes still use it
+    pub fn delete_service(&self, id_or_name: &str) -> Result<(), GatewayError> {
+        let mut desired = self.desired.write();
+        let id = find_id(&desired.services, id_or_name, |s| &s.name)
+            .ok_or_else(|| GatewayError::ServiceNotFound(id_or_name.to_string()))?;
+        if desired.routes.values().any(|r| r.service.as_ref().is_some_and(|s| s.id == id)) {
+            return Err(GatewayError::ConfigError(format!("service {} still has routes", id_or_name)));
+        }
+        desired.services.remove(&id);
+        desired.plugins.retain(|_, p| p.service.as_ref().is_none_or(|s| s.id != id));
+        Ok(())
+    }
+
+    pub fn list_services(&self) -> Vec<Service> {
+        self.desired.read().services.values().cloned().collect()
+    }
+
+    // -------------------------------------------------------------------------
+    // Routes
+    // -------------------------------------------------------------------------
+
+    pub fn create_route(&self, mut route: Route) -> Result<Route, GatewayError> {
+        let mut desired = self.desired.write();
+        if desired.routes.values().any(|r| r.name == route.name) {
+            return Err(GatewayError::ConfigError(format!("route {} already exists", route.name)));
+        }
+        resolve_service(&desired, &mut route)?;
+        let id = route.id.get_or_insert_with(new_id).clone();
+        desired.routes.insert(id, route.clone());
+        Ok(route)
+    }
+
+    pub fn get_route(&self, id_or_name: &str) -> Result<Route, GatewayError> {
+        let desired = self.desired.read();
+        find(&desired.routes, id_or_name, |r| &r.name)
+            .cloned()
+            .ok_or_else(|| GatewayError::RouteNotFound(id_or_name.to_string()))
+    }
+
+    pub fn update_route(&self, id_or_name: &str, mut route: Route) -> Result<Route, GatewayError> {
+        let mut desired = self.desired.write();
+        let id = find_id(&desired.routes, id_or_name, |r| &r.name)
+            .ok_or_else(|| GatewayError::RouteNotFound(id_or_name.to_string()))?;
+        resolve_service(&desired, &mut route)?;
+        route.id = Some(id.clone());
+        desired.routes.insert(id, route.clone());
+        Ok(route)
+    }
+
+    /// Delete a route and its plugins
+    pub fn delete_route(&self, id_or_name: &str) -> Result<(), GatewayError> {
+        let mut desired = self.desired.write();
+        let id = find_id(&desired.routes, id_or_name, |r| &r.name)
+            .ok_or_else(|| GatewayError::RouteNotFound(id_or_name.to_string()))?;
+        desired.routes.remove(&id);
+        desired.plugins.retain(|_, p| p.route.as_ref().is_none_or(|r| r.id != id));
+        Ok(())
+    }
+
+    pub fn list_routes(&self) -> Vec<Route> {
+        self.desired.read().routes.values().cloned().collect()
+    }
+
+    pub fn list_service_routes(&self, service_id: &str) -> Result<Vec<Route>, GatewayError> {
+        let desired = self.desired.read();
+        let id = find_id(&desired.services, service_id, |s| &s.name)
+            .ok_or_else(|| GatewayError::ServiceNotFound(service_id.to_string()))?;
+        Ok(desired.routes.values()
+            .filter(|r| r.service.as_ref().is_some_and(|s| s.id == id))
+            .cloned()
+            .collect())
+    }
+
+    // -------------------------------------------------------------------------
+    // Consumers
+    // -------------------------------------------------------------------------
+
+    pub fn create_consumer(&self, mut consumer: Consumer) -> Result<Consumer, GatewayError> {
+        let mut desired = self.desired.write();
+        if desired.consumers.values().any(|c| c.username == consumer.username) {
+            return Err(GatewayError::ConfigError(format!("consumer {} already exists", consumer.username)));
+        }
+        let id = consumer.id.get_or_insert_with(new_id).clone();
+        desired.consumers.insert(id, consumer.clone());
+        Ok(consumer)
+    }
+
+    pub fn get_consumer(&self, id_or_username: &str) -> Result<Consumer, GatewayError> {
+        let desired = self.desired.read();
+        find(&desired.consumers, id_or_username, |c| &c.username)
+            .cloned()
+            .ok_or_else(|| GatewayError::ConsumerNotFound(id_or_username.to_string()))
+    }
+
+    pub fn update_consumer(&self, id_or_username: &str, mut consumer: Consumer) -> Result<Consumer, GatewayError> {
+        let mut desired = self.desired.write();
+        let id = find_id(&desired.consumers, id_or_username, |c| &c.username)
+            .ok_or_else(|| GatewayError::ConsumerNotFound(id_or_username.to_string()))?;
+        consumer.id = Some(id.clone());
+        desired.consumers.insert(id, consumer.clone());
+        Ok(consumer)
+    }
+
+    /// Delete a consumer and its plugins
+    pub fn delete_consumer(&self, id_or_username: &str) -> Result<(), GatewayError> {
+        let mut desired = self.desired.write();
+        let id = find_id(&desired.consumers, id_or_username, |c| &c.username)
+            .ok_or_else(|| GatewayError::ConsumerNotFound(id_or_username.to_string()))?;
+        desired.consumers.remove(&id);
+        desired.plugins.retain(|_, p| p.consumer.as_ref().is_none_or(|c| c.id != id));
+        Ok(())
+    }
+
+    pub fn list_consumers(&self) -> Vec<Consumer> {
+        self.desired.read().consumers.values().cloned().collect()
+    }
+
+    // -------------------------------------------------------------------------
+    // Plugins
+    // -------------------------------------------------------------------------
+
+    pub fn create_plugin(&self, mut plugin: Plugin) -> Result<Plugin, GatewayError> {
+        let mut desired = self.desired.write();
+        resolve_scope(&desired, &mut plugin)?;
+        let id = plugin.id.get_or_insert_with(new_id).clone();
+        desired.plugins.insert(id, plugin.clone());
+        Ok(plugin)
+    }
+
+    pub fn get_plugin(&self, id: &str) -> Result<Plugin, GatewayError> {
+        self.desired.read().plugins.get(id)
+            .cloned()
+            .ok_or_else(|| GatewayError::ConfigError(format!("plugin {} not found", id)))
+    }
+
+    pub fn update_plugin(&self, id: &str, mut plugin: Plugin) -> Result<Plugin, GatewayError> {
+        let mut desired = self.desired.write();
+        if !desired.plugins.contains_key(id) {
+            return Err(GatewayError::ConfigError(format!("plugin {} not found", id)));
+        }
+        resolve_scope(&desired, &mut plugin)?;
+        plugin.id = Some(id.to_string());
+        desired.plugins.insert(id.to_string(), plugin.clone());
+        Ok(plugin)
+    }
+
+    pub fn delete_plugin(&self, id: &str) -> Result<(), GatewayError> {
+        self.desired.write().plugins.remove(id)
+            .map(|_| ())
+            .ok_or_else(|| GatewayError::ConfigError(format!("plugin {} not found", id)))
+    }
+
+    /// Plugins matching a filter
+    pub fn list_plugins(&self, filter: impl Fn(&Plugin) -> bool) -> Vec<Plugin> {
+        self.desired.read().plugins.values().filter(|p| filter(p)).cloned().collect()
+    }
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+fn find<'a, T>(entities: &'a BTreeMap<String, T>, id_or_name: &str, name: impl Fn(&T) -> &String) -> Option<&'a T> {
+    entities.get(id_or_name)
+        .or_else(|| entities.values().find(|e| name(e) == id_or_name))
+}
+
+fn find_id<T>(entities: &BTreeMap<String, T>, id_or_name: &str, name: impl Fn(&T) -> &String) -> Option<String> {
+    if entities.contains_key(id_or_name) {
+        return Some(id_or_name.to_string());
+    }
+    entities.iter().find(|(_, e)| name(e) == id_or_name).map(|(id, _)| id.clone())
+}
+
+/// Point a route's service reference at the staged service's ID
+fn resolve_service(config: &DeclarativeConfig, route: &mut Route) -> Result<(), GatewayError> {
+    if let Some(service) = route.service.as_mut() {
+        service.id = find_id(&config.services, &service.id, |s| &s.name)
+            .ok_or_else(|| GatewayError::ServiceNotFound(service.id.clone()))?;
+    }
+    Ok(())
+}
+
+/// Point a plugin's scope references at staged entity IDs
+fn resolve_scope(config: &DeclarativeConfig, plugin: &mut Plugin) -> Result<(), GatewayError> {
+    if let Some(service) = plugin.service.as_mut() {
+        service.id = find_id(&config.services, &service.id, |s| &s.name)
+            .ok_or_else(|| GatewayError::ServiceNotFound(service.id.clone()))?;
+    }
+    if let Some(route) = plugin.route.as_mut() {
+        route.id = find_id(&config.routes, &route.id, |r| &r.name)
+            .ok_or_else(|| GatewayError::RouteNotFound(route.id.clone()))?;
+    }
+    if let Some(consumer) = plugin.consumer.as_mut() {
+        consumer.id = find_id(&config.consumers, &consumer.id, |c| &c.username)
+            .ok_or_else(|| GatewayError::ConsumerNotFound(consumer.id.clone()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Store with a service, a route on it, a consumer and plugins at each scope
+    fn staged() -> DeclarativeStore {
+        let store = DeclarativeStore::new();
+        store.create_service(Service::new("orders", "orders.internal", 8080)).unwrap();
+        store.create_route(Route::new("orders-api", "orders").with_paths(vec!["/orders".into()])).unwrap();
+        store.create_route(Route::new("orders-admin", "orders").with_paths(vec!["/admin/orders".into()])).unwrap();
+        store.create_consumer(Consumer::new("acme")).unwrap();
+        store.create_plugin(Plugin::new("cors", json!({}))).unwrap();
+        store.create_plugin(Plugin::new("rate-limiting", json!({ "minute": 60 })).for_route("orders-api")).unwrap();
+        store.create_plugin(Plugin::new("acl", json!({ "allow": ["ops"] })).for_route("orders-admin")).unwrap();
+        store.create_plugin(Plugin::new("key-auth", json!({})).for_consumer("acme")).unwrap();
+        store
+    }
+
+    fn plugin_names(store: &DeclarativeStore) -> Vec<String> {
+        let mut names: Vec<String> = store.list_plugins(|_| true).into_iter().map(|p| p.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_everything_is_new_before_first_sync() {
+        let store = staged();
+        let diff = store.pending();
+        assert_eq!(diff.changes.len(), 8);
+        assert_eq!(diff.count(ChangeAction::Create), 8);
+    }
+
+    #[test]
+    fn test_no_changes_after_sync() {
+        let store = staged();
+        store.mark_applied(store.desired());
+        assert!(store.pending().is_empty());
+
+        // Timestamps Kong stamps on the applied entities are not changes
+        let mut applied = store.desired();
+        for service in applied.services.values_mut() {
+            service.created_at = Some(1_700_000_000);
+            service.updated_at = Some(1_700_000_001);
+        }
+        store.mark_applied(applied);
+        assert!(store.pending().is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_creates_updates_and_deletes() {
+        let store = staged();
+        store.mark_applied(store.desired());
+
+        store.update_service("orders", Service::new("orders", "orders.internal", 9090)).unwrap();
+        store.create_consumer(Consumer::new("globex")).unwrap();
+        store.delete_consumer("acme").unwrap();
+
+        let diff = store.pending();
+        let mut changes: Vec<(EntityKind, String, ChangeAction)> = diff.changes.iter()
+            .map(|c| (c.kind, c.name.clone(), c.action))
+            .collect();
+        changes.sort_by_key(|(kind, name, _)| (format!("{:?}", kind), name.clone()));
+        assert_eq!(changes, vec![
+            (EntityKind::Consumer, "acme".to_string(), ChangeAction::Delete),
+            (EntityKind::Consumer, "globex".to_string(), ChangeAction::Create),
+            (EntityKind::Plugin, "key-auth".to_string(), ChangeAction::Delete),
+            (EntityKind::Service, "orders".to_string(), ChangeAction::Update),
+        ]);
+    }
+
+    #[test]
+    fn test_deletes_cascade_only_to_scoped_plugins() {
+        let store = staged();
+
+        store.delete_route("orders-admin").unwrap();
+        assert_eq!(plugin_names(&store), vec!["cors", "key-auth", "rate-limiting"]);
+
+        // Routes block deleting their service
+        assert!(store.delete_service("orders").is_err());
+        store.create_plugin(Plugin::new("request-size-limiting", json!({})).for_service("orders")).unwrap();
+        store.delete_route("orders-api").unwrap();
+        store.delete_service("orders").unwrap();
+        assert_eq!(plugin_names(&store), vec!["cors", "key-auth"]);
+
+        store.delete_consumer("acme").unwrap();
+        assert_eq!(plugin_names(&store), vec!["cors"]);
+    }
+
+    #[test]
+    fn test_references_resolve_by_name() {
+        let store = staged();
+        let service = store.get_service("orders").unwrap();
+        let route = store.get_route("orders-api").unwrap();
+        assert_eq!(route.service.unwrap().id, service.id.unwrap());
+
+        assert!(matches!(store.create_route(Route::new("orphan", "missing")), Err(GatewayError::ServiceNotFound(_))));
+        assert!(matches!(
+            store.create_plugin(Plugin::new("acl", json!({})).for_consumer("nobody")),
+            Err(GatewayError::ConsumerNotFound(_))
+        ));
+        assert!(store.create_service(Service::new("orders", "other.internal", 80)).is_err());
+    }
+
+    #[test]
+    fn test_yaml_nests_single_scope_plugins() {
+        let store = staged();
+        let route_id = store.get_route("orders-api").unwrap().id.unwrap();
+        store.create_plugin(Plugin::new("request-transformer", json!({})).for_route(&route_id).for_consumer("acme")).unwrap();
+
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&store.desired().to_yaml().unwrap()).unwrap();
+        assert_eq!(yaml["_format_version"], "3.0");
+
+        let service = &yaml["services"][0];
+        assert_eq!(service["name"], "orders");
+        let routes = service["routes"].as_sequence().unwrap();
+        assert_eq!(routes.len(), 2);
+        let api = routes.iter().find(|r| r["name"] == "orders-api").unwrap();
+        assert!(api.get("service").is_none());
+        assert_eq!(api["plugins"][0]["name"], "rate-limiting");
+        assert!(api["plugins"][0].get("route").is_none());
+
+        assert_eq!(yaml["consumers"][0]["plugins"][0]["name"], "key-auth");
+
+        // Global and multi-scope plugins stay top-level, scoped by name
+        let plugins = yaml["plugins"].as_sequence().unwrap();
+        assert_eq!(plugins.len(), 2);
+        let transformer = plugins.iter().find(|p| p["name"] == "request-transformer").unwrap();
+        assert_eq!(transformer["route"], "orders-api");
+        assert_eq!(transformer["consumer"], "acme");
+    }
+}
@@ -1,7 +1,18 @@
 //! Kong Admin API Client
 //!
 //! HTTP client for Kong Gateway Admin API.
+//!
+//! In declarative mode (DB-less Kong) Services, Routes, Consumers and
+//! Plugins are staged locally and pushed as one config by
+//! [`KongClient::sync`]; see [`declarative`].
+
+pub mod declarative;
+
+pub use declarative::{ChangeAction, ConfigDiff, DeclarativeConfig, EntityChange, EntityKind, SyncReport};
+
+use declarative::DeclarativeStore;
 
+use crate::mtls::{CaCertificate, MtlsCredential};
 use crate::{
     ApiKey, Consumer, GatewayError, GatewayStatus, JwtCredential, Plugin, Route, Service,
 };
@@ -13,6 +24,8 @@ pub struct KongClient {
     client: reqwest::Client,
     base_url: String,
     api_key: Option<String>,
+    /// Staged config when Kong runs DB-less
+    declarative: Option<DeclarativeStore>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,9 +56,23 @@ impl KongClient {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key,
+            declarative: None,
         })
     }
     
+    /// Create a client for a DB-less Kong: entity writes are staged and
+    /// only reach Kong on [`KongClient::sync`]
+    pub fn declarative(base_url: &str, api_key: Option<String>) -> Result<Self, GatewayError> {
+        let mut client = Self::new(base_url, api_key)?;
+        client.declarative = Some(DeclarativeStore::new());
+        Ok(client)
+    }
+    
+    /// Whether entity writes are staged for a declarative sync
+    pub fn is_declarative(&self) -> bool {
+        self.declarative.is_some()
+    }
+    
     /// Get Kong status
     pub async fn get_status(&self) -> Result<GatewayStatus, GatewayError> {
         let url = format!("{}/status", self.base_url);
@@ -58,30 +85,45 @@ impl KongClient {
     
     /// Create a service
     pub async fn create_service(&self, service: Service) -> Result<Service, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.create_service(service);
+        }
         let url = format!("{}/services", self.base_url);
         self.post(&url, &service).await
     }
     
     /// Get a service by ID or name
     pub async fn get_service(&self, id_or_name: &str) -> Result<Service, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.get_service(id_or_name);
+        }
         let url = format!("{}/services/{}", self.base_url, id_or_name);
         self.get(&url).await
     }
     
     /// Update a service
     pub async fn update_service(&self, id: &str, service: Service) -> Result<Service, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.update_service(id, service);
+        }
         let url = format!("{}/services/{}", self.base_url, id);
         self.patch(&url, &service).await
     }
     
     /// Delete a service
     pub async fn delete_service(&self, id: &str) -> Result<(), GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.delete_service(id);
+        }
         let url = format!("{}/services/{}", self.base_url, id);
         self.delete(&url).await
     }
     
     /// List all services
     pub async fn list_services(&self) -> Result<Vec<Service>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return Ok(store.list_services());
+        }
         let url = format!("{}/services", self.base_url);
         let result: KongList<Service> = self.get(&url).await?;
         Ok(result.data)
@@ -93,30 +135,45 @@ impl KongClient {
     
     /// Create a route
     pub async fn create_route(&self, route: Route) -> Result<Route, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.create_route(route);
+        }
         let url = format!("{}/routes", self.base_url);
         self.post(&url, &route).await
     }
     
     /// Get a route by ID or name
     pub async fn get_route(&self, id_or_name: &str) -> Result<Route, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.get_route(id_or_name);
+        }
         let url = format!("{}/routes/{}", self.base_url, id_or_name);
         self.get(&url).await
     }
     
     /// Update a route
     pub async fn update_route(&self, id: &str, route: Route) -> Result<Route, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.update_route(id, route);
+        }
         let url = format!("{}/routes/{}", self.base_url, id);
         self.patch(&url, &route).await
     }
     
     /// Delete a route
     pub async fn delete_route(&self, id: &str) -> Result<(), GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.delete_route(id);
+        }
         let url = format!("{}/routes/{}", self.base_url, id);
         self.delete(&url).await
     }
     
     /// List all routes
     pub async fn list_routes(&self) -> Result<Vec<Route>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return Ok(store.list_routes());
+        }
         let url = format!("{}/routes", self.base_url);
         let result: KongList<Route> = self.get(&url).await?;
         Ok(result.data)
@@ -124,6 +181,9 @@ impl KongClient {
     
     /// List routes for a service
     pub async fn list_service_routes(&self, service_id: &str) -> Result<Vec<Route>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.list_service_routes(service_id);
+        }
         let url = format!("{}/services/{}/routes", self.base_url, service_id);
         let result: KongList<Route> = self.get(&url).await?;
         Ok(result.data)
@@ -135,30 +195,45 @@ impl KongClient {
     
     /// Create a consumer
     pub async fn create_consumer(&self, consumer: Consumer) -> Result<Consumer, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.create_consumer(consumer);
+        }
         let url = format!("{}/consumers", self.base_url);
         self.post(&url, &consumer).await
     }
     
     /// Get a consumer by ID or username
     pub async fn get_consumer(&self, id_or_username: &str) -> Result<Consumer, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.get_consumer(id_or_username);
+        }
         let url = format!("{}/consumers/{}", self.base_url, id_or_username);
         self.get(&url).await
     }
     
     /// Update a consumer
     pub async fn update_consumer(&self, id: &str, consumer: Consumer) -> Result<Consumer, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.update_consumer(id, consumer);
+        }
         let url = format!("{}/consumers/{}", self.base_url, id);
         self.patch(&url, &consumer).await
     }
     
     /// Delete a consumer
     pub async fn delete_consumer(&self, id: &str) -> Result<(), GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.delete_consumer(id);
+        }
         let url = format!("{}/consumers/{}", self.base_url, id);
         self.delete(&url).await
     }
     
     /// List all consumers
     pub async fn list_consumers(&self) -> Result<Vec<Consumer>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return Ok(store.list_consumers());
+        }
         let url = format!("{}/consumers", self.base_url);
         let result: KongList<Consumer> = self.get(&url).await?;
         Ok(result.data)
@@ -170,30 +245,45 @@ impl KongClient {
     
     /// Create a plugin
     pub async fn create_plugin(&self, plugin: Plugin) -> Result<Plugin, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.create_plugin(plugin);
+        }
         let url = format!("{}/plugins", self.base_url);
         self.post(&url, &plugin).await
     }
     
     /// Get a plugin by ID
     pub async fn get_plugin(&self, id: &str) -> Result<Plugin, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.get_plugin(id);
+        }
         let url = format!("{}/plugins/{}", self.base_url, id);
         self.get(&url).await
     }
     
     /// Update a plugin
     pub async fn update_plugin(&self, id: &str, plugin: Plugin) -> Result<Plugin, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.update_plugin(id, plugin);
+        }
         let url = format!("{}/plugins/{}", self.base_url, id);
         self.patch(&url, &plugin).await
     }
     
     /// Delete a plugin
     pub async fn delete_plugin(&self, id: &str) -> Result<(), GatewayError> {
+        if let Some(store) = &self.declarative {
+            return store.delete_plugin(id);
+        }
         let url = format!("{}/plugins/{}", self.base_url, id);
         self.delete(&url).await
     }
     
     /// List all plugins
     pub async fn list_plugins(&self) -> Result<Vec<Plugin>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return Ok(store.list_plugins(|_| true));
+        }
         let url = format!("{}/plugins", self.base_url);
         let result: KongList<Plugin> = self.get(&url).await?;
         Ok(result.data)
@@ -201,17 +291,106 @@ impl KongClient {
     
     /// List plugins for a service
     pub async fn list_service_plugins(&self, service_id: &str) -> Result<Vec<Plugin>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return Ok(store.list_plugins(|p| p.service.as_ref().is_some_and(|s| s.id == service_id)));
+        }
         let url = format!("{}/services/{}/plugins", self.base_url, service_id);
         let result: KongList<Plugin> = self.get(&url).await?;
         Ok(result.data)
     }
     
+    /// List plugins scoped to a consumer
+    pub async fn list_consumer_plugins(&self, consumer_id: &str) -> Result<Vec<Plugin>, GatewayError> {
+        if let Some(store) = &self.declarative {
+            return Ok(store.list_plugins(|p| p.consumer.as_ref().is_some_and(|c| c.id == consumer_id)));
+        }
+        let url = format!("{}/consumers/{}/plugins", self.base_url, consumer_id);
+        let result: KongList<Plugin> = self.get(&url).await?;
+        Ok(result.data)
+    }
+    
+    // =========================================================================
+    // Declarative Sync
+    // =========================================================================
+    
+    /// Staged configuration (declarative mode only)
+    pub fn desired_config(&self) -> Option<DeclarativeConfig> {
+        self.declarative.as_ref().map(|store| store.desired())
+    }
+    
+    /// Replace the staged configuration wholesale
+    pub fn load_config(&self, config: DeclarativeConfig) -> Result<(), GatewayError> {
+        self.store()?.replace(config);
+        Ok(())
+    }
+    
+    /// Render the staged configuration as a decK file
+    pub fn render_config(&self) -> Result<String, GatewayError> {
+        self.store()?.desired().to_yaml()
+    }
+    
+    /// Changes the next sync would push
+    pub fn pending_changes(&self) -> Result<ConfigDiff, GatewayError> {
+        Ok(self.store()?.pending())
+    }
+    
+    /// Push the staged configuration to `/config` if any entity changed
+    /// since the last accepted sync; an unchanged config never reloads Kong
+    pub async fn sync(&self) -> Result<SyncReport, GatewayError> {
+        let store = self.store()?;
+        let desired = store.desired();
+        let diff = store.pending();
+        
+        if diff.is_empty() {
+            return Ok(SyncReport { diff, reloaded: false, synced_at: chrono::Utc::now() });
+        }
+        
+        #[derive(Serialize)]
+        struct ConfigBody {
+            config: String,
+        }
+        
+        // check_hash lets Kong skip the reload too if it already runs this
+        // config, e.g. after a control-plane restart
+        let url = format!("{}/config?check_hash=1", self.base_url);
+        let body = ConfigBody { config: desired.to_yaml()? };
+        let response = self.client.post(&url).json(&body).send().await?;
+        
+        let status = response.status();
+        if !status.is_success() && status != reqwest::StatusCode::NOT_MODIFIED {
+            let text = response.text().await.unwrap_or_default();
+            return Err(GatewayError::KongError(format!("{}: {}", status, text)));
+        }
+        
+        tracing::info!(
+            "Declarative config synced: {} created, {} updated, {} deleted",
+            diff.count(ChangeAction::Create),
+            diff.count(ChangeAction::Update),
+            diff.count(ChangeAction::Delete),
+        );
+        store.mark_applied(desired);
+        Ok(SyncReport { diff, reloaded: status != reqwest::StatusCode::NOT_MODIFIED, synced_at: chrono::Utc::now() })
+    }
+    
+    fn store(&self) -> Result<&DeclarativeStore, GatewayError> {
+        self.declarative.as_ref()
+            .ok_or_else(|| GatewayError::ConfigError("Kong client is not in declarative mode".into()))
+    }
+    
+    fn admin_only(&self, entity: &str) -> Result<(), GatewayError> {
+        if self.declarative.is_some() {
+            return Err(GatewayError::ConfigError(format!("{} are not managed in declarative mode", entity)));
+        }
+        Ok(())
+    }
+    
     // =========================================================================
     // Credentials
     // =========================================================================
     
     /// Create API key for consumer
     pub async fn create_api_key(&self, consumer_id: &str) -> Result<ApiKey, GatewayError> {
+        self.admin_only("API keys")?;
         let url = format!("{}/consumers/{}/key-auth", self.base_url, consumer_id);
         
         #[derive(Serialize)]
@@ -222,6 +401,7 @@ impl KongClient {
     
     /// Create API key with specific value
     pub async fn create_api_key_with_value(&self, consumer_id: &str, key: &str) -> Result<ApiKey, GatewayError> {
+        self.admin_only("API keys")?;
         let url = format!("{}/consumers/{}/key-auth", self.base_url, consumer_id);
         
         #[derive(Serialize)]
@@ -241,12 +421,14 @@ impl KongClient {
     
     /// Delete API key
     pub async fn delete_api_key(&self, consumer_id: &str, key_id: &str) -> Result<(), GatewayError> {
+        self.admin_only("API keys")?;
         let url = format!("{}/consumers/{}/key-auth/{}", self.base_url, consumer_id, key_id);
         self.delete(&url).await
     }
     
     /// Create JWT credential for consumer
     pub async fn create_jwt_credential(&self, consumer_id: &str) -> Result<JwtCredential, GatewayError> {
+        self.admin_only("JWT credentials")?;
         let url = format!("{}/consumers/{}/jwt", self.base_url, consumer_id);
         
         #[derive(Serialize)]
@@ -264,18 +446,77 @@ impl KongClient {
         Ok(result.data)
     }
     
+    // =========================================================================
+    // CA Certificates and mTLS Credentials
+    // =========================================================================
+    
+    /// Upload a trusted CA certificate
+    pub async fn create_ca_certificate(&self, cert: CaCertificate) -> Result<CaCertificate, GatewayError> {
+        self.admin_only("CA certificates")?;
+        let url = format!("{}/ca_certificates", self.base_url);
+        self.post(&url, &cert).await
+    }
+    
+    /// Get a CA certificate by ID
+    pub async fn get_ca_certificate(&self, id: &str) -> Result<CaCertificate, GatewayError> {
+        let url = format!("{}/ca_certificates/{}", self.base_url, id);
+        self.get(&url).await
+    }
+    
+    /// List CA certificates
+    pub async fn list_ca_certificates(&self) -> Result<Vec<CaCertificate>, GatewayError> {
+        let url = format!("{}/ca_certificates", self.base_url);
+        let result: KongList<CaCertificate> = self.get(&url).await?;
+        Ok(result.data)
+    }
+    
+    /// Delete a CA certificate; Kong refuses while a plugin still trusts it
+    pub async fn delete_ca_certificate(&self, id: &str) -> Result<(), GatewayError> {
+        self.admin_only("CA certificates")?;
+        let url = format!("{}/ca_certificates/{}", self.base_url, id);
+        self.delete(&url).await
+    }
+    
+    /// Map a client certificate subject to a consumer
+    pub async fn create_mtls_credential(&self, consumer_id: &str, credential: MtlsCredential) -> Result<MtlsCredential, GatewayError> {
+        self.admin_only("mTLS credentials")?;
+        let url = format!("{}/consumers/{}/mtls-auth", self.base_url, consumer_id);
+        self.post(&url, &credential).await
+    }
+    
+    /// List certificate mappings for a consumer, every page
+    pub async fn list_mtls_credentials(&self, consumer_id: &str) -> Result<Vec<MtlsCredential>, GatewayError> {
+        let url = format!("{}/consumers/{}/mtls-auth", self.base_url, consumer_id);
+        self.get_all(&url).await
+    }
+    
+    /// List certificate mappings across all consumers, every page
+    pub async fn list_all_mtls_credentials(&self) -> Result<Vec<MtlsCredential>, GatewayError> {
+        let url = format!("{}/mtls-auths", self.base_url);
+        self.get_all(&url).await
+    }
+    
+    /// Delete a certificate mapping
+    pub async fn delete_mtls_credential(&self, consumer_id: &str, credential_id: &str) -> Result<(), GatewayError> {
+        self.admin_only("mTLS credentials")?;
+        let url = format!("{}/consumers/{}/mtls-auth/{}", self.base_url, consumer_id, credential_id);
+        self.delete(&url).await
+    }
+    
     // =========================================================================
     // Upstreams and Targets (Load Balancing)
     // =========================================================================
     
     /// Create an upstream
     pub async fn create_upstream(&self, upstream: Upstream) -> Result<Upstream, GatewayError> {
+        self.admin_only("upstreams")?;
         let url = format!("{}/upstreams", self.base_url);
         self.post(&url, &upstream).await
     }
     
     /// Add a target to an upstream
     pub async fn add_target(&self, upstream_id: &str, target: Target) -> Result<Target, GatewayError> {
+        self.admin_only("upstream targets")?;
         let url = format!("{}/upstreams/{}/targets", self.base_url, upstream_id);
         self.post(&url, &target).await
     }
@@ -287,10 +528,38 @@ impl KongClient {
         Ok(result.data)
     }
     
+    /// Change a target's weight; weight 0 stops traffic to it
+    pub async fn update_target_weight(&self, upstream_id: &str, target_id: &str, weight: u32) -> Result<Target, GatewayError> {
+        self.admin_only("upstream targets")?;
+        let url = format!("{}/upstreams/{}/targets/{}", self.base_url, upstream_id, target_id);
+        self.patch(&url, &serde_json::json!({ "weight": weight })).await
+    }
+    
+    /// Delete an upstream and its targets
+    pub async fn delete_upstream(&self, id: &str) -> Result<(), GatewayError> {
+        self.admin_only("upstreams")?;
+        let url = format!("{}/upstreams/{}", self.base_url, id);
+        self.delete(&url).await
+    }
+    
     // =========================================================================
     // HTTP Helpers
     // =========================================================================
     
+    /// Follow `next` links until the listing is exhausted
+    async fn get_all<T: DeserializeOwned>(&self, url: &str) -> Result<Vec<T>, GatewayError> {
+        let mut items = Vec::new();
+        let mut url = url.to_string();
+        loop {
+            let page: KongList<T> = self.get(&url).await?;
+            items.extend(page.data);
+            match page.next.as_deref().map(|next| next_page_url(&self.base_url, next)) {
+                Some(next) if next != url => url = next,
+                _ => return Ok(items),
+            }
+        }
+    }
+    
     async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, GatewayError> {
         let response = self.client.get(url).send().await?;
         
@@ -425,3 +694,39 @@ impl Target {
         }
     }
 }
+
+/// Absolute URL of a `next` link. Kong returns an absolute path, which
+/// already carries any workspace prefix, so it replaces the base URL's path.
+fn next_page_url(base_url: &str, next: &str) -> String {
+    if next.starts_with("http://") || next.starts_with("https://") {
+        return next.to_string();
+    }
+    let host_start = base_url.find("://").map(|i| i + 3).unwrap_or(0);
+    let origin = match base_url[host_start..].find('/') {
+        Some(path_start) => &base_url[..host_start + path_start],
+        None => base_url,
+    };
+    format!("{}/{}", origin, next.trim_start_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_page_url() {
+        assert_eq!(
+            next_page_url("http://kong:8001", "/mtls-auths?offset=abc"),
+            "http://kong:8001/mtls-auths?offset=abc"
+        );
+        // Workspace prefixes come back in the link itself
+        assert_eq!(
+            next_page_url("https://kong:8444/team-a", "/team-a/mtls-auths?offset=abc"),
+            "https://kong:8444/team-a/mtls-auths?offset=abc"
+        );
+        assert_eq!(
+            next_page_url("http://kong:8001", "http://other:8001/mtls-auths?offset=abc"),
+            "http://other:8001/mtls-auths?offset=abc"
+        );
+    }
+}
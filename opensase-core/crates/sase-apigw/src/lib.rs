@@ -4,9 +4,10 @@
 //! - API authentication (OAuth2, JWT, API keys, mTLS)
 //! - Rate limiting and quota management
 //! - Request/response transformation
-//! - API versioning and routing
+//! - API versioning, weighted routing and canary releases
+//! - Service and route provisioning from OpenAPI documents
 //! - DDoS protection for APIs
-//! - API analytics and monitoring
+//! - API analytics, monitoring and per-consumer usage billing
 //!
 //! # Architecture
 //!
@@ -51,11 +52,19 @@ pub mod transform;
 pub mod analytics;
 pub mod routing;
 pub mod ddos;
+pub mod mtls;
+pub mod graphql;
+pub mod canary;
+pub mod openapi;
 
 // Re-exports
-pub use kong::KongClient;
+pub use kong::{KongClient, ConfigDiff, DeclarativeConfig, SyncReport};
 pub use auth::{AuthManager, AuthMethod};
-pub use ratelimit::{RateLimiter, RateLimitPolicy};
+pub use ratelimit::{LimitRequest, LimitRule, LimitScope, RateLimitEngine, RateLimiter, RateLimitPolicy};
+pub use canary::{CanaryDeployment, CanaryHealth, CanaryPolicy, CanaryState, TrafficSplit, WeightedBackend};
+pub use graphql::{GraphqlGuard, GraphqlPolicy, GraphqlViolation};
+pub use openapi::{ImportOptions, ImportPlan, ImportReport, OpenApiSpec};
+pub use mtls::{CaCertificate, CaRotation, MtlsAuthConfig, MtlsCredential, RevocationCheckMode, RevokedCertificate};
 
 // =============================================================================
 // Core Types
@@ -70,12 +79,18 @@ pub struct GatewayConfig {
     pub kong_admin_key: Option<String>,
     /// Workspace (Kong Enterprise)
     pub workspace: Option<String>,
+    /// Kong runs DB-less; entities are pushed as declarative config
+    #[serde(default)]
+    pub declarative: bool,
     /// Default rate limit
     pub default_rate_limit: RateLimitConfig,
     /// Authentication settings
     pub auth: AuthConfig,
     /// Analytics settings
     pub analytics: AnalyticsConfig,
+    /// File the mTLS revocation list is kept in across restarts
+    #[serde(default)]
+    pub mtls_revocation_file: Option<std::path::PathBuf>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -86,6 +101,18 @@ pub struct RateLimitConfig {
     pub requests_per_day: u32,
 }
 
+impl RateLimitConfig {
+    /// Limits granted by a tenant's billing plan
+    pub fn for_plan(limits: &sase_tenant::ApiRateLimits) -> Self {
+        Self {
+            requests_per_second: limits.requests_per_second,
+            requests_per_minute: limits.requests_per_minute,
+            requests_per_hour: limits.requests_per_minute.saturating_mul(60).min(limits.requests_per_day),
+            requests_per_day: limits.requests_per_day,
+        }
+    }
+}
+
 impl Default for RateLimitConfig {
     fn default() -> Self {
         Self {
@@ -208,6 +235,9 @@ pub struct Route {
     pub headers: Option<HashMap<String, Vec<String>>>,
     pub strip_path: bool,
     pub preserve_host: bool,
+    /// Order among regex paths; higher is tried first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_priority: Option<i32>,
     pub service: Option<ServiceRef>,
     pub tags: Option<Vec<String>>,
     pub created_at: Option<i64>,
@@ -231,6 +261,7 @@ impl Route {
             headers: None,
             strip_path: true,
             preserve_host: false,
+            regex_priority: None,
             service: Some(ServiceRef { id: service_id.to_string() }),
             tags: None,
             created_at: None,
@@ -252,6 +283,11 @@ impl Route {
         self.methods = Some(methods);
         self
     }
+    
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = Some(tags);
+        self
+    }
 }
 
 /// Kong Consumer representation
@@ -349,15 +385,25 @@ pub struct ApiGateway {
     auth_manager: auth::AuthManager,
     rate_limiter: ratelimit::RateLimiter,
     analytics: analytics::AnalyticsCollector,
+    mtls: mtls::MtlsManager,
+    canaries: canary::CanaryManager,
 }
 
 impl ApiGateway {
     /// Create new API Gateway instance
     pub async fn new(config: GatewayConfig) -> Result<Self, GatewayError> {
-        let kong = kong::KongClient::new(&config.kong_admin_url, config.kong_admin_key.clone())?;
+        let kong = if config.declarative {
+            kong::KongClient::declarative(&config.kong_admin_url, config.kong_admin_key.clone())?
+        } else {
+            kong::KongClient::new(&config.kong_admin_url, config.kong_admin_key.clone())?
+        };
         let auth_manager = auth::AuthManager::new(config.auth.clone());
         let rate_limiter = ratelimit::RateLimiter::new(config.default_rate_limit.clone());
         let analytics = analytics::AnalyticsCollector::new(config.analytics.clone());
+        let mtls = match &config.mtls_revocation_file {
+            Some(path) => mtls::MtlsManager::persistent(path)?,
+            None => mtls::MtlsManager::new(),
+        };
         
         Ok(Self {
             config,
@@ -365,6 +411,8 @@ impl ApiGateway {
             auth_manager,
             rate_limiter,
             analytics,
+            mtls,
+            canaries: canary::CanaryManager::new(),
         })
     }
     
@@ -378,9 +426,27 @@ impl ApiGateway {
         self.kong.create_route(route).await
     }
     
-    /// Register a consumer
+    /// Provision a service, routes and request validators from an OpenAPI
+    /// 3.x document; importing again converges on the new document
+    pub async fn import_openapi(
+        &self,
+        document: &str,
+        options: &ImportOptions,
+    ) -> Result<ImportReport, GatewayError> {
+        let spec = OpenApiSpec::parse(document)?;
+        ImportPlan::build(&spec, options)?.apply(&self.kong).await
+    }
+    
+    /// Register a consumer. A consumer whose `custom_id` is a tenant id has
+    /// its API usage billed to that tenant.
     pub async fn register_consumer(&self, consumer: Consumer) -> Result<Consumer, GatewayError> {
-        self.kong.create_consumer(consumer).await
+        let consumer = self.kong.create_consumer(consumer).await?;
+        if let (Some(id), Some(tenant)) = (consumer.id.as_deref(), consumer.custom_id.as_deref()) {
+            if let Ok(tenant_id) = uuid::Uuid::parse_str(tenant) {
+                self.analytics.usage().bind_consumer(id, tenant_id);
+            }
+        }
+        Ok(consumer)
     }
     
     /// Enable a plugin
@@ -429,6 +495,508 @@ impl ApiGateway {
         self.kong.create_plugin(plugin).await
     }
     
+    /// Enable mTLS client certificate authentication for a service,
+    /// trusting every CA in a PEM bundle
+    pub async fn enable_mtls_auth(
+        &self,
+        service_id: &str,
+        ca_bundle: &str,
+        config: MtlsAuthConfig,
+    ) -> Result<Plugin, GatewayError> {
+        let certs = mtls::split_pem_bundle(ca_bundle);
+        if certs.is_empty() {
+            return Err(GatewayError::ConfigError("CA bundle contains no certificates".to_string()));
+        }
+        
+        let mut ca_ids = Vec::new();
+        for pem in certs {
+            ca_ids.extend(self.upload_ca_certificate(&pem).await?.id);
+        }
+        
+        let plugin = Plugin::new("mtls-auth", config.to_plugin_config(&ca_ids)).for_service(service_id);
+        self.kong.create_plugin(plugin).await
+    }
+    
+    /// Upload a CA certificate, reusing an earlier upload of the same one
+    pub async fn upload_ca_certificate(&self, pem: &str) -> Result<CaCertificate, GatewayError> {
+        let cert = CaCertificate::from_pem(pem)?.with_tags(vec!["opensase-mtls".to_string()]);
+        let existing = self.kong.list_ca_certificates().await?
+            .into_iter()
+            .find(|c| c.cert_digest.is_some() && c.cert_digest == cert.cert_digest);
+        
+        match existing {
+            Some(existing) => Ok(existing),
+            None => self.kong.create_ca_certificate(cert).await,
+        }
+    }
+    
+    /// Map a client certificate subject to a consumer, optionally only when
+    /// issued by one CA
+    pub async fn map_client_certificate(
+        &self,
+        consumer_id: &str,
+        subject_name: &str,
+        ca_certificate_id: Option<&str>,
+    ) -> Result<MtlsCredential, GatewayError> {
+        if self.mtls.is_revoked(subject_name) {
+            return Err(GatewayError::AuthError(format!("Certificate subject revoked: {}", subject_name)));
+        }
+        
+        let mut credential = MtlsCredential::new(subject_name);
+        if let Some(ca_id) = ca_certificate_id {
+            credential = credential.issued_by(ca_id);
+        }
+        self.kong.create_mtls_credential(consumer_id, credential).await
+    }
+    
+    /// Client certificate subjects mapped to a consumer
+    pub async fn list_client_certificates(&self, consumer_id: &str) -> Result<Vec<MtlsCredential>, GatewayError> {
+        self.kong.list_mtls_credentials(consumer_id).await
+    }
+    
+    /// Remove a client certificate mapping
+    pub async fn unmap_client_certificate(&self, consumer_id: &str, credential_id: &str) -> Result<(), GatewayError> {
+        self.kong.delete_mtls_credential(consumer_id, credential_id).await
+    }
+    
+    /// Revoke a client certificate subject: its mappings are removed from
+    /// every consumer and it cannot be mapped again until reinstated
+    pub async fn revoke_client_certificate(
+        &self,
+        subject_name: &str,
+        reason: &str,
+    ) -> Result<RevokedCertificate, GatewayError> {
+        let mut entry = RevokedCertificate {
+            subject_name: subject_name.trim().to_string(),
+            reason: reason.to_string(),
+            revoked_at: Utc::now(),
+            removed_credentials: 0,
+        };
+        // Listed first so concurrent mapping attempts are refused
+        self.mtls.revoke(entry.clone())?;
+        
+        let mappings = self.kong.list_all_mtls_credentials().await?
+            .into_iter()
+            .filter(|c| c.subject_name.trim().eq_ignore_ascii_case(&entry.subject_name));
+        for credential in mappings {
+            if let (Some(id), Some(consumer)) = (credential.id, credential.consumer) {
+                self.kong.delete_mtls_credential(&consumer.id, &id).await?;
+                entry.removed_credentials += 1;
+            }
+        }
+        
+        tracing::info!(
+            "Revoked client certificate {} ({} mappings removed): {}",
+            entry.subject_name, entry.removed_credentials, reason
+        );
+        self.mtls.revoke(entry.clone())?;
+        Ok(entry)
+    }
+    
+    /// Take a subject off the revocation list; mappings must be recreated
+    pub fn reinstate_client_certificate(&self, subject_name: &str) -> Result<Option<RevokedCertificate>, GatewayError> {
+        self.mtls.reinstate(subject_name)
+    }
+    
+    /// Revocation list, most recent first
+    pub fn revoked_certificates(&self) -> Vec<RevokedCertificate> {
+        self.mtls.revoked()
+    }
+    
+    /// Restore a persisted revocation list
+    pub fn load_revoked_certificates(&self, entries: Vec<RevokedCertificate>) -> Result<(), GatewayError> {
+        self.mtls.load_revoked(entries)
+    }
+    
+    /// Change how an mTLS plugin checks CRL/OCSP revocation
+    pub async fn set_revocation_check_mode(
+        &self,
+        plugin_id: &str,
+        mode: RevocationCheckMode,
+    ) -> Result<Plugin, GatewayError> {
+        let mut plugin = self.kong.get_plugin(plugin_id).await?;
+        plugin.config["revocation_check_mode"] = serde_json::json!(mode);
+        self.kong.update_plugin(plugin_id, plugin).await
+    }
+    
+    /// Start rotating an mTLS plugin to a new CA: both CAs are trusted and
+    /// mappings pinned to the old CA are copied to the new one, so clients
+    /// can move over at their own pace
+    pub async fn begin_ca_rotation(
+        &self,
+        plugin_id: &str,
+        old_ca_id: &str,
+        new_ca_pem: &str,
+    ) -> Result<CaRotation, GatewayError> {
+        if self.mtls.rotation(plugin_id).is_some() {
+            return Err(GatewayError::ConfigError(format!("CA rotation already in progress for plugin {}", plugin_id)));
+        }
+        
+        let mut plugin = self.kong.get_plugin(plugin_id).await?;
+        let mut trusted = mtls::trusted_ca_ids(&plugin.config);
+        if !trusted.iter().any(|id| id == old_ca_id) {
+            return Err(GatewayError::ConfigError(format!("Plugin {} does not trust CA {}", plugin_id, old_ca_id)));
+        }
+        
+        let new_ca_id = self.upload_ca_certificate(new_ca_pem).await?.id
+            .ok_or_else(|| GatewayError::KongError("CA certificate created without an ID".to_string()))?;
+        if new_ca_id == old_ca_id {
+            return Err(GatewayError::ConfigError("New CA certificate is the one being rotated out".to_string()));
+        }
+        
+        if !trusted.contains(&new_ca_id) {
+            trusted.push(new_ca_id.clone());
+            plugin.config["ca_certificates"] = serde_json::json!(trusted);
+            self.kong.update_plugin(plugin_id, plugin).await?;
+        }
+        
+        let mut remapped = Vec::new();
+        for credential in self.kong.list_all_mtls_credentials().await? {
+            if credential.ca_certificate.as_ref().map(|ca| ca.id.as_str()) != Some(old_ca_id) {
+                continue;
+            }
+            let (Some(id), Some(consumer)) = (credential.id, credential.consumer) else { continue };
+            let mut copy = MtlsCredential::new(&credential.subject_name).issued_by(&new_ca_id);
+            copy.tags = credential.tags;
+            let created = self.kong.create_mtls_credential(&consumer.id, copy).await?;
+            remapped.push((consumer.id, id, created.id.unwrap_or_default()));
+        }
+        
+        let rotation = CaRotation {
+            plugin_id: plugin_id.to_string(),
+            old_ca_id: old_ca_id.to_string(),
+            new_ca_id,
+            remapped,
+            started_at: Utc::now(),
+        };
+        tracing::info!(
+            "CA rotation started on plugin {}: {} -> {} ({} mappings copied)",
+            plugin_id, rotation.old_ca_id, rotation.new_ca_id, rotation.remapped.len()
+        );
+        self.mtls.start_rotation(rotation.clone())?;
+        Ok(rotation)
+    }
+    
+    /// Finish a CA rotation: stop trusting the old CA and drop the mappings
+    /// pinned to it
+    pub async fn complete_ca_rotation(&self, plugin_id: &str) -> Result<CaRotation, GatewayError> {
+        let rotation = self.pending_rotation(plugin_id)?;
+        self.retire_ca(plugin_id, &rotation.old_ca_id).await?;
+        
+        for (consumer_id, old_credential, _) in &rotation.remapped {
+            if let Err(e) = self.kong.delete_mtls_credential(consumer_id, old_credential).await {
+                tracing::warn!("Failed to remove mTLS mapping {}: {}", old_credential, e);
+            }
+        }
+        
+        self.mtls.finish_rotation(plugin_id);
+        Ok(rotation)
+    }
+    
+    /// Roll a CA rotation back: the new CA and the copied mappings are removed
+    pub async fn abort_ca_rotation(&self, plugin_id: &str) -> Result<CaRotation, GatewayError> {
+        let rotation = self.pending_rotation(plugin_id)?;
+        self.retire_ca(plugin_id, &rotation.new_ca_id).await?;
+        
+        for (consumer_id, _, new_credential) in &rotation.remapped {
+            if let Err(e) = self.kong.delete_mtls_credential(consumer_id, new_credential).await {
+                tracing::warn!("Failed to remove mTLS mapping {}: {}", new_credential, e);
+            }
+        }
+        
+        self.mtls.finish_rotation(plugin_id);
+        Ok(rotation)
+    }
+    
+    /// In-flight CA rotation for a plugin
+    pub fn ca_rotation(&self, plugin_id: &str) -> Option<CaRotation> {
+        self.mtls.rotation(plugin_id)
+    }
+    
+    fn pending_rotation(&self, plugin_id: &str) -> Result<CaRotation, GatewayError> {
+        self.mtls.rotation(plugin_id)
+            .ok_or_else(|| GatewayError::ConfigError(format!("No CA rotation in progress for plugin {}", plugin_id)))
+    }
+    
+    /// Drop a CA from a plugin's trust list, then delete it unless another
+    /// plugin still trusts it
+    async fn retire_ca(&self, plugin_id: &str, ca_id: &str) -> Result<(), GatewayError> {
+        let mut plugin = self.kong.get_plugin(plugin_id).await?;
+        let trusted: Vec<String> = mtls::trusted_ca_ids(&plugin.config)
+            .into_iter()
+            .filter(|id| id != ca_id)
+            .collect();
+        plugin.config["ca_certificates"] = serde_json::json!(trusted);
+        self.kong.update_plugin(plugin_id, plugin).await?;
+        
+        if let Err(e) = self.kong.delete_ca_certificate(ca_id).await {
+            tracing::info!("Keeping CA certificate {}: {}", ca_id, e);
+        }
+        Ok(())
+    }
+    
+    // =========================================================================
+    // Traffic Splitting and Canary Releases
+    // =========================================================================
+    
+    /// Spread a route's traffic over weighted `host:port` backends
+    pub async fn split_traffic(
+        &self,
+        route_id: &str,
+        backends: Vec<WeightedBackend>,
+    ) -> Result<TrafficSplit, GatewayError> {
+        canary::validate_backends(&backends)?;
+        if self.canaries.split(route_id).is_some() {
+            return Err(GatewayError::ConfigError(format!("Route {} is already split", route_id)));
+        }
+        
+        let mut route = self.kong.get_route(route_id).await?;
+        let original_service_id = route.service.as_ref()
+            .map(|s| s.id.clone())
+            .ok_or_else(|| GatewayError::ConfigError(format!("Route {} has no service", route_id)))?;
+        let original = self.kong.get_service(&original_service_id).await?;
+        let tags = vec![canary::SPLIT_TAG.to_string(), format!("route:{}", route_id)];
+        
+        let name = format!("{}-split", route.name);
+        let mut upstream = kong::Upstream::new(&name);
+        upstream.tags = Some(tags.clone());
+        let upstream = self.kong.create_upstream(upstream).await?;
+        let upstream_id = upstream.id.clone().unwrap_or(upstream.name);
+        
+        let mut created = Vec::new();
+        for backend in backends {
+            let mut target = kong::Target::new(&backend.target, backend.weight);
+            target.tags = Some(tags.clone());
+            match self.kong.add_target(&upstream_id, target).await {
+                Ok(target) => created.push(WeightedBackend { target_id: target.id, ..backend }),
+                Err(e) => {
+                    let _ = self.kong.delete_upstream(&upstream_id).await;
+                    return Err(e);
+                }
+            }
+        }
+        
+        // Same protocol, path and timeouts as the original, resolved
+        // through the upstream instead of a fixed host
+        let mut service = Service {
+            id: None,
+            name: name.clone(),
+            host: name,
+            tags: Some(tags),
+            created_at: None,
+            updated_at: None,
+            ..original
+        };
+        service.enabled = true;
+        let service = match self.kong.create_service(service).await {
+            Ok(service) => service,
+            Err(e) => {
+                let _ = self.kong.delete_upstream(&upstream_id).await;
+                return Err(e);
+            }
+        };
+        let split_service_id = service.id.unwrap_or(service.name);
+        
+        route.service = Some(ServiceRef { id: split_service_id.clone() });
+        if let Err(e) = self.kong.update_route(route_id, route).await {
+            let _ = self.kong.delete_service(&split_service_id).await;
+            let _ = self.kong.delete_upstream(&upstream_id).await;
+            return Err(e);
+        }
+        
+        let split = TrafficSplit {
+            route_id: route_id.to_string(),
+            original_service_id,
+            split_service_id,
+            upstream_id,
+            backends: created,
+            created_at: Utc::now(),
+        };
+        self.canaries.insert_split(split.clone());
+        tracing::info!("Split route {} across {} backends", route_id, split.backends.len());
+        Ok(split)
+    }
+    
+    /// Change one backend's weight in a split
+    pub async fn set_backend_weight(
+        &self,
+        route_id: &str,
+        target: &str,
+        weight: u32,
+    ) -> Result<TrafficSplit, GatewayError> {
+        let mut split = self.active_split(route_id)?;
+        let backend = split.backends.iter_mut()
+            .find(|b| b.target == target)
+            .ok_or_else(|| GatewayError::ConfigError(format!("Route {} has no backend {}", route_id, target)))?;
+        backend.weight = weight;
+        let target_id = backend.target_id.clone().unwrap_or_else(|| target.to_string());
+        canary::validate_backends(&split.backends)?;
+        
+        self.kong.update_target_weight(&split.upstream_id, &target_id, weight).await?;
+        self.canaries.update_split(split.clone());
+        Ok(split)
+    }
+    
+    /// Point a split route back at its original service and delete the
+    /// split service and upstream
+    pub async fn remove_traffic_split(&self, route_id: &str) -> Result<TrafficSplit, GatewayError> {
+        let split = self.active_split(route_id)?;
+        
+        let mut route = self.kong.get_route(route_id).await?;
+        route.service = Some(ServiceRef { id: split.original_service_id.clone() });
+        self.kong.update_route(route_id, route).await?;
+        
+        if let Err(e) = self.kong.delete_service(&split.split_service_id).await {
+            tracing::warn!("Failed to delete split service {}: {}", split.split_service_id, e);
+        }
+        if let Err(e) = self.kong.delete_upstream(&split.upstream_id).await {
+            tracing::warn!("Failed to delete split upstream {}: {}", split.upstream_id, e);
+        }
+        
+        self.canaries.remove_split(route_id);
+        Ok(split)
+    }
+    
+    /// Active traffic split on a route
+    pub fn traffic_split(&self, route_id: &str) -> Option<TrafficSplit> {
+        self.canaries.split(route_id)
+    }
+    
+    /// Start a canary: the route's current service keeps `100 - percentage`
+    /// of traffic and the canary backend gets the rest
+    pub async fn start_canary(
+        &self,
+        route_id: &str,
+        canary_target: &str,
+        percentage: u32,
+        policy: CanaryPolicy,
+    ) -> Result<CanaryDeployment, GatewayError> {
+        if !(1..100).contains(&percentage) {
+            return Err(GatewayError::ConfigError("Canary percentage must be between 1 and 99".to_string()));
+        }
+        
+        let route = self.kong.get_route(route_id).await?;
+        let service_id = route.service.as_ref()
+            .map(|s| s.id.clone())
+            .ok_or_else(|| GatewayError::ConfigError(format!("Route {} has no service", route_id)))?;
+        let service = self.kong.get_service(&service_id).await?;
+        let primary_target = format!("{}:{}", service.host, service.port);
+        
+        let split = self.split_traffic(route_id, vec![
+            WeightedBackend::new(&primary_target, 100 - percentage),
+            WeightedBackend::new(canary_target, percentage),
+        ]).await?;
+        
+        let canary = CanaryDeployment {
+            route_id: route_id.to_string(),
+            split,
+            primary_target,
+            canary_target: canary_target.to_string(),
+            policy,
+            state: CanaryState::Running,
+            health: CanaryHealth::default(),
+            started_at: Utc::now(),
+            finished_at: None,
+            rollback_reason: None,
+        };
+        self.canaries.upsert_canary(canary.clone());
+        tracing::info!("Started canary {} on route {} at {}%", canary_target, route_id, percentage);
+        Ok(canary)
+    }
+    
+    /// Shift a running canary to a new traffic percentage
+    pub async fn set_canary_percentage(&self, route_id: &str, percentage: u32) -> Result<CanaryDeployment, GatewayError> {
+        if !(1..100).contains(&percentage) {
+            return Err(GatewayError::ConfigError("Canary percentage must be between 1 and 99".to_string()));
+        }
+        let canary = self.running_canary(route_id)?;
+        
+        self.set_backend_weight(route_id, &canary.canary_target, percentage).await?;
+        self.set_backend_weight(route_id, &canary.primary_target, 100 - percentage).await?;
+        self.running_canary(route_id)
+    }
+    
+    /// Make the canary the route's only backend: the original service is
+    /// repointed at the canary and the split removed
+    pub async fn promote_canary(&self, route_id: &str) -> Result<CanaryDeployment, GatewayError> {
+        let mut canary = self.running_canary(route_id)?;
+        let (host, port) = canary.canary_target.rsplit_once(':')
+            .and_then(|(host, port)| Some((host.to_string(), port.parse::<u16>().ok()?)))
+            .ok_or_else(|| GatewayError::ConfigError(format!("Invalid canary target {}", canary.canary_target)))?;
+        
+        let mut service = self.kong.get_service(&canary.split.original_service_id).await?;
+        service.host = host;
+        service.port = port;
+        self.kong.update_service(&canary.split.original_service_id, service).await?;
+        self.remove_traffic_split(route_id).await?;
+        
+        canary.state = CanaryState::Promoted;
+        canary.finished_at = Some(Utc::now());
+        self.canaries.upsert_canary(canary.clone());
+        tracing::info!("Promoted canary {} on route {}", canary.canary_target, route_id);
+        Ok(canary)
+    }
+    
+    /// Send all traffic back to the primary and discard the canary
+    pub async fn rollback_canary(&self, route_id: &str, reason: &str) -> Result<CanaryDeployment, GatewayError> {
+        let mut canary = self.running_canary(route_id)?;
+        self.remove_traffic_split(route_id).await?;
+        
+        canary.state = CanaryState::RolledBack;
+        canary.finished_at = Some(Utc::now());
+        canary.rollback_reason = Some(reason.to_string());
+        self.canaries.upsert_canary(canary.clone());
+        tracing::warn!("Rolled back canary {} on route {}: {}", canary.canary_target, route_id, reason);
+        Ok(canary)
+    }
+    
+    /// Canary error rate over its policy window
+    pub fn canary_health(&self, route_id: &str) -> Option<CanaryHealth> {
+        let canary = self.canaries.canary(route_id)?;
+        let since = Utc::now() - chrono::Duration::seconds(canary.policy.window_secs as i64);
+        let (requests, errors) = self.analytics.upstream_errors(&canary.canary_target, since);
+        Some(CanaryHealth::new(requests, errors))
+    }
+    
+    /// Refresh the health of every running canary and roll back those over
+    /// their error threshold; call periodically. Returns canaries rolled back.
+    pub async fn check_canaries(&self) -> Result<Vec<CanaryDeployment>, GatewayError> {
+        let mut rolled_back = Vec::new();
+        for mut canary in self.canaries.running() {
+            let Some(health) = self.canary_health(&canary.route_id) else {
+                continue;
+            };
+            let breach = health.breach(&canary.policy);
+            canary.health = health;
+            self.canaries.upsert_canary(canary.clone());
+            
+            match breach {
+                Some(reason) if canary.policy.auto_rollback => {
+                    rolled_back.push(self.rollback_canary(&canary.route_id, &reason).await?);
+                }
+                Some(reason) => tracing::warn!("Canary on route {} unhealthy: {}", canary.route_id, reason),
+                None => {}
+            }
+        }
+        Ok(rolled_back)
+    }
+    
+    /// Latest canary on a route, finished or not
+    pub fn canary(&self, route_id: &str) -> Option<CanaryDeployment> {
+        self.canaries.canary(route_id)
+    }
+    
+    fn active_split(&self, route_id: &str) -> Result<TrafficSplit, GatewayError> {
+        self.canaries.split(route_id)
+            .ok_or_else(|| GatewayError::ConfigError(format!("Route {} has no traffic split", route_id)))
+    }
+    
+    fn running_canary(&self, route_id: &str) -> Result<CanaryDeployment, GatewayError> {
+        self.canaries.canary(route_id)
+            .filter(|c| c.state == CanaryState::Running)
+            .ok_or_else(|| GatewayError::ConfigError(format!("No canary running on route {}", route_id)))
+    }
+    
     /// Enable rate limiting for a service
     pub async fn enable_rate_limiting(
         &self,
@@ -449,6 +1017,61 @@ impl ApiGateway {
         self.kong.create_plugin(plugin).await
     }
     
+    /// Enable GraphQL protection for a service, replacing any policy
+    /// installed earlier
+    pub async fn enable_graphql_protection(
+        &self,
+        service_id: &str,
+        policy: &GraphqlPolicy,
+    ) -> Result<Plugin, GatewayError> {
+        let mut plugin = policy.to_plugin(service_id)?;
+        plugin.tags = Some(vec!["opensase-graphql".to_string()]);
+        
+        let existing = self.kong.list_plugins().await?
+            .into_iter()
+            .find(|p| p.name == "pre-function"
+                && p.service.as_ref().is_some_and(|s| s.id == service_id)
+                && p.tags.as_ref().is_some_and(|t| t.iter().any(|t| t == "opensase-graphql")));
+        
+        match existing.and_then(|p| p.id) {
+            Some(id) => self.kong.update_plugin(&id, plugin).await,
+            None => self.kong.create_plugin(plugin).await,
+        }
+    }
+    
+    /// Mirror a tenant's plan limits into its consumer-scoped rate-limiting
+    /// plugin, updating the existing plugin when there is one
+    pub async fn sync_consumer_rate_limit(
+        &self,
+        consumer_id: &str,
+        entitlements: &sase_tenant::Entitlements,
+    ) -> Result<Plugin, GatewayError> {
+        let config = RateLimitConfig::for_plan(&entitlements.api_limits);
+        let mut plugin = Plugin::new("rate-limiting", serde_json::json!({
+            "second": config.requests_per_second,
+            "minute": config.requests_per_minute,
+            "hour": config.requests_per_hour,
+            "day": config.requests_per_day,
+            "limit_by": "consumer",
+            "policy": "local",
+            "fault_tolerant": true,
+            "hide_client_headers": false
+        })).for_consumer(consumer_id);
+        plugin.tags = Some(vec![
+            "opensase-plan".to_string(),
+            format!("plan:{:?}", entitlements.tier).to_lowercase(),
+        ]);
+        
+        let existing = self.kong.list_consumer_plugins(consumer_id).await?
+            .into_iter()
+            .find(|p| p.name == "rate-limiting");
+        
+        match existing.and_then(|p| p.id) {
+            Some(id) => self.kong.update_plugin(&id, plugin).await,
+            None => self.kong.create_plugin(plugin).await,
+        }
+    }
+    
     /// Enable request transformation
     pub async fn enable_request_transform(
         &self,
@@ -560,6 +1183,27 @@ impl ApiGateway {
         self.kong.create_plugin(plugin).await
     }
     
+    /// Record a request served through the gateway (e.g. from an `http-log`
+    /// feed); canary health is computed from these
+    pub fn record_request(&self, log: analytics::RequestLog) {
+        self.analytics.record(log);
+    }
+    
+    /// Bill a consumer's API usage to a tenant
+    pub fn bind_consumer_tenant(&self, consumer_id: &str, tenant_id: uuid::Uuid) {
+        self.analytics.usage().bind_consumer(consumer_id, tenant_id);
+    }
+    
+    /// Per-consumer usage for the month containing `month`
+    pub fn consumer_usage(&self, month: chrono::NaiveDate) -> Vec<analytics::ConsumerUsage> {
+        self.analytics.usage().monthly_usage(month)
+    }
+    
+    /// Push per-consumer API usage accrued since the last flush to billing
+    pub async fn flush_api_usage(&self, sink: &dyn analytics::UsageSink) -> analytics::UsageFlushReport {
+        self.analytics.usage().flush(sink, Utc::now()).await
+    }
+    
     /// Generate API key for a consumer
     pub async fn create_api_key(&self, consumer_id: &str) -> Result<ApiKey, GatewayError> {
         self.kong.create_api_key(consumer_id).await
@@ -570,6 +1214,17 @@ impl ApiGateway {
         self.kong.create_jwt_credential(consumer_id).await
     }
     
+    /// Push staged entities to a DB-less Kong; reloads only when something
+    /// changed since the last sync
+    pub async fn sync_config(&self) -> Result<SyncReport, GatewayError> {
+        self.kong.sync().await
+    }
+    
+    /// Current staged configuration as a decK file
+    pub fn export_config(&self) -> Result<String, GatewayError> {
+        self.kong.render_config()
+    }
+    
     /// Get gateway status
     pub async fn status(&self) -> Result<GatewayStatus, GatewayError> {
         self.kong.get_status().await
@@ -674,6 +1329,95 @@ pub enum GatewayError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
     
+    #[error("Rate limit store error: {0}")]
+    StoreError(String),
+    
+    #[error("mTLS revocation list error: {0}")]
+    RevocationListError(String),
+    
     #[error("HTTP error: {0}")]
     HttpError(#[from] reqwest::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn credential(id: &str, subject_name: &str, consumer_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "subject_name": subject_name,
+            "ca_certificate": null,
+            "consumer": { "id": consumer_id },
+            "tags": null,
+        })
+    }
+
+    async fn gateway(kong: &MockServer, revocation_file: Option<std::path::PathBuf>) -> ApiGateway {
+        ApiGateway::new(GatewayConfig {
+            kong_admin_url: kong.uri(),
+            kong_admin_key: None,
+            workspace: None,
+            declarative: false,
+            default_rate_limit: RateLimitConfig::default(),
+            auth: AuthConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            mtls_revocation_file: revocation_file,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_revocation_removes_mappings_on_every_page() {
+        let kong = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/mtls-auths"))
+            .and(query_param_is_missing("offset"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [
+                    credential("cred-1", "client.example.com", "consumer-1"),
+                    credential("cred-2", "other.example.com", "consumer-1"),
+                ],
+                "next": "/mtls-auths?offset=page-2",
+            })))
+            .mount(&kong)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/mtls-auths"))
+            .and(query_param("offset", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [credential("cred-3", "CLIENT.example.com", "consumer-2")],
+                "next": null,
+            })))
+            .mount(&kong)
+            .await;
+        for (consumer, id) in [("consumer-1", "cred-1"), ("consumer-2", "cred-3")] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/consumers/{}/mtls-auth/{}", consumer, id)))
+                .respond_with(ResponseTemplate::new(204))
+                .expect(1)
+                .mount(&kong)
+                .await;
+        }
+
+        let revocation_file = std::env::temp_dir().join(format!("mtls-revoked-{}.json", uuid::Uuid::new_v4()));
+        let first = gateway(&kong, Some(revocation_file.clone())).await;
+        let entry = first.revoke_client_certificate("client.example.com", "key compromise").await.unwrap();
+        assert_eq!(entry.removed_credentials, 2);
+        assert!(matches!(
+            first.map_client_certificate("consumer-3", "Client.Example.com", None).await,
+            Err(GatewayError::AuthError(_))
+        ));
+
+        // A restarted gateway still refuses the subject
+        let restarted = gateway(&kong, Some(revocation_file.clone())).await;
+        assert!(matches!(
+            restarted.map_client_certificate("consumer-3", "client.example.com", None).await,
+            Err(GatewayError::AuthError(_))
+        ));
+        std::fs::remove_file(&revocation_file).unwrap();
+    }
+}
@@ -0,0 +1,432 @@
+//! mTLS Client Certificate Authentication
+//!
+//! Client certificates are verified by Kong's `mtls-auth` plugin against
+//! uploaded CA certificates and mapped to consumers by subject name.
+//!
+//! Only explicitly mapped subjects authenticate by default (`consumer_by`
+//! is empty), so revoking a subject here takes effect as soon as its
+//! mappings are gone. CRL/OCSP checks on the certificate itself are set
+//! with [`RevocationCheckMode`]. The revocation list is written to a file
+//! when [`MtlsManager::persistent`] is used, so a restart cannot let a
+//! revoked subject be mapped again.
+//!
+//! CA bundles rotate in two phases so clients never see a gap:
+//!
+//! ```text
+//!   begin:    trust [old, new]   mappings pinned to old are copied to new
+//!   complete: trust [new]        old mappings and old CA are deleted
+//!   abort:    trust [old]        copied mappings and new CA are deleted
+//! ```
+
+use crate::{ConsumerRef, GatewayError};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// =============================================================================
+// Kong Entities
+// =============================================================================
+
+/// Kong CA certificate entity
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaCertificate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// PEM-encoded certificate
+    pub cert: String,
+    /// Hex SHA-256 of the DER certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_digest: Option<String>,
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+}
+
+impl CaCertificate {
+    /// Parse a PEM certificate and compute its digest
+    pub fn from_pem(pem: &str) -> Result<Self, GatewayError> {
+        let digest = pem_digest(pem)?;
+        Ok(Self {
+            id: None,
+            cert: pem.trim().to_string(),
+            cert_digest: Some(digest),
+            tags: None,
+            created_at: None,
+        })
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = Some(tags);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaCertificateRef {
+    pub id: String,
+}
+
+/// Client certificate subject mapped to a consumer (`mtls-auth` credential)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MtlsCredential {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Certificate subject CN or SAN to match
+    pub subject_name: String,
+    /// Only accept the subject when issued by this CA
+    pub ca_certificate: Option<CaCertificateRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consumer: Option<ConsumerRef>,
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+}
+
+impl MtlsCredential {
+    pub fn new(subject_name: &str) -> Self {
+        Self {
+            id: None,
+            subject_name: subject_name.to_string(),
+            ca_certificate: None,
+            consumer: None,
+            tags: None,
+            created_at: None,
+        }
+    }
+
+    /// Pin the mapping to one issuing CA
+    pub fn issued_by(mut self, ca_certificate_id: &str) -> Self {
+        self.ca_certificate = Some(CaCertificateRef { id: ca_certificate_id.to_string() });
+        self
+    }
+}
+
+// =============================================================================
+// Plugin Configuration
+// =============================================================================
+
+/// How Kong checks certificate revocation via CRL/OCSP
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RevocationCheckMode {
+    /// No revocation checks
+    Skip,
+    /// Reject revoked certificates; allow when the responder is unreachable
+    IgnoreCaError,
+    /// Reject unless the certificate is confirmed not revoked
+    Strict,
+}
+
+/// `mtls-auth` plugin settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MtlsAuthConfig {
+    /// Consumer fields matched against the subject when no credential maps
+    /// it; empty means only mapped subjects authenticate
+    pub consumer_by: Vec<String>,
+    /// Authenticate any valid certificate without a consumer
+    pub skip_consumer_lookup: bool,
+    pub revocation_check_mode: RevocationCheckMode,
+    /// Seconds to cache certificate-to-consumer lookups
+    pub cache_ttl: u32,
+    /// Timeout for CRL/OCSP requests, in milliseconds
+    pub http_timeout: u32,
+    /// Consumer used when authentication fails
+    pub anonymous: Option<String>,
+    /// Accept chains that end at an intermediate CA
+    pub allow_partial_chain: bool,
+    /// Advertise trusted CA names in the TLS handshake
+    pub send_ca_dn: bool,
+}
+
+impl Default for MtlsAuthConfig {
+    fn default() -> Self {
+        Self {
+            consumer_by: Vec::new(),
+            skip_consumer_lookup: false,
+            revocation_check_mode: RevocationCheckMode::IgnoreCaError,
+            cache_ttl: 60,
+            http_timeout: 30000,
+            anonymous: None,
+            allow_partial_chain: false,
+            send_ca_dn: true,
+        }
+    }
+}
+
+impl MtlsAuthConfig {
+    /// Plugin config trusting the given CA certificate IDs
+    pub fn to_plugin_config(&self, ca_certificate_ids: &[String]) -> serde_json::Value {
+        serde_json::json!({
+            "ca_certificates": ca_certificate_ids,
+            "consumer_by": self.consumer_by,
+            "skip_consumer_lookup": self.skip_consumer_lookup,
+            "revocation_check_mode": self.revocation_check_mode,
+            "cache_ttl": self.cache_ttl,
+            "http_timeout": self.http_timeout,
+            "anonymous": self.anonymous,
+            "allow_partial_chain": self.allow_partial_chain,
+            "send_ca_dn": self.send_ca_dn,
+        })
+    }
+}
+
+// =============================================================================
+// Revocation and Rotation State
+// =============================================================================
+
+/// Locally revoked client certificate subject
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RevokedCertificate {
+    pub subject_name: String,
+    pub reason: String,
+    pub revoked_at: chrono::DateTime<chrono::Utc>,
+    /// Consumer mappings removed on revocation
+    pub removed_credentials: usize,
+}
+
+/// In-flight CA rotation on one `mtls-auth` plugin
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaRotation {
+    pub plugin_id: String,
+    pub old_ca_id: String,
+    pub new_ca_id: String,
+    /// (consumer ID, credential pinned to the old CA, its copy pinned to the new CA)
+    pub remapped: Vec<(String, String, String)>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Revocation list and rotation bookkeeping
+pub struct MtlsManager {
+    revoked: parking_lot::RwLock<HashMap<String, RevokedCertificate>>,
+    rotations: parking_lot::RwLock<HashMap<String, CaRotation>>,
+    /// Where the revocation list is persisted, if anywhere
+    revocation_file: Option<PathBuf>,
+}
+
+impl MtlsManager {
+    pub fn new() -> Self {
+        Self {
+            revoked: parking_lot::RwLock::new(HashMap::new()),
+            rotations: parking_lot::RwLock::new(HashMap::new()),
+            revocation_file: None,
+        }
+    }
+
+    /// Revocation list kept in `path`, loaded now and rewritten on every
+    /// change
+    pub fn persistent(path: impl AsRef<Path>) -> Result<Self, GatewayError> {
+        let path = path.as_ref();
+        let entries: Vec<RevokedCertificate> = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| revocation_error(path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(revocation_error(path, e)),
+        };
+        let manager = Self { revocation_file: Some(path.to_path_buf()), ..Self::new() };
+        manager.revoked.write().extend(entries.into_iter().map(|e| (subject_key(&e.subject_name), e)));
+        Ok(manager)
+    }
+
+    /// Whether a subject is on the revocation list
+    pub fn is_revoked(&self, subject_name: &str) -> bool {
+        self.revoked.read().contains_key(&subject_key(subject_name))
+    }
+
+    pub fn revoke(&self, entry: RevokedCertificate) -> Result<(), GatewayError> {
+        let mut revoked = self.revoked.write();
+        revoked.insert(subject_key(&entry.subject_name), entry);
+        self.persist(&revoked)
+    }
+
+    /// Take a subject off the revocation list; existing mappings are not
+    /// restored
+    pub fn reinstate(&self, subject_name: &str) -> Result<Option<RevokedCertificate>, GatewayError> {
+        let mut revoked = self.revoked.write();
+        let entry = revoked.remove(&subject_key(subject_name));
+        if entry.is_some() {
+            self.persist(&revoked)?;
+        }
+        Ok(entry)
+    }
+
+    /// Revocation list, most recent first
+    pub fn revoked(&self) -> Vec<RevokedCertificate> {
+        let mut revoked: Vec<_> = self.revoked.read().values().cloned().collect();
+        revoked.sort_by_key(|r| std::cmp::Reverse(r.revoked_at));
+        revoked
+    }
+
+    /// Replace the revocation list, e.g. from a persisted copy
+    pub fn load_revoked(&self, entries: Vec<RevokedCertificate>) -> Result<(), GatewayError> {
+        let mut revoked = self.revoked.write();
+        revoked.clear();
+        for entry in entries {
+            revoked.insert(subject_key(&entry.subject_name), entry);
+        }
+        self.persist(&revoked)
+    }
+
+    /// Rewrite the revocation file; written aside and renamed so a crash
+    /// never leaves a truncated list
+    fn persist(&self, revoked: &HashMap<String, RevokedCertificate>) -> Result<(), GatewayError> {
+        let Some(path) = &self.revocation_file else { return Ok(()) };
+        let mut entries: Vec<_> = revoked.values().collect();
+        entries.sort_by(|a, b| a.subject_name.cmp(&b.subject_name));
+        let json = serde_json::to_vec_pretty(&entries).map_err(|e| revocation_error(path, e))?;
+        let staging = path.with_extension("tmp");
+        std::fs::write(&staging, json).map_err(|e| revocation_error(path, e))?;
+        std::fs::rename(&staging, path).map_err(|e| revocation_error(path, e))
+    }
+
+    pub fn rotation(&self, plugin_id: &str) -> Option<CaRotation> {
+        self.rotations.read().get(plugin_id).cloned()
+    }
+
+    pub fn start_rotation(&self, rotation: CaRotation) -> Result<(), GatewayError> {
+        let mut rotations = self.rotations.write();
+        if rotations.contains_key(&rotation.plugin_id) {
+            return Err(GatewayError::ConfigError(format!(
+                "CA rotation already in progress for plugin {}", rotation.plugin_id
+            )));
+        }
+        rotations.insert(rotation.plugin_id.clone(), rotation);
+        Ok(())
+    }
+
+    pub fn finish_rotation(&self, plugin_id: &str) -> Option<CaRotation> {
+        self.rotations.write().remove(plugin_id)
+    }
+}
+
+impl Default for MtlsManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn revocation_error(path: &Path, e: impl std::fmt::Display) -> GatewayError {
+    GatewayError::RevocationListError(format!("{}: {}", path.display(), e))
+}
+
+/// Subjects compare case-insensitively, as Kong matches them
+fn subject_key(subject_name: &str) -> String {
+    subject_name.trim().to_lowercase()
+}
+
+/// Split a PEM bundle into individual certificates
+pub fn split_pem_bundle(bundle: &str) -> Vec<String> {
+    const END: &str = "-----END CERTIFICATE-----";
+    bundle.split_inclusive(END)
+        .filter_map(|chunk| chunk.find("-----BEGIN CERTIFICATE-----").map(|start| &chunk[start..]))
+        .filter(|cert| cert.ends_with(END))
+        .map(|cert| cert.to_string())
+        .collect()
+}
+
+/// Hex SHA-256 of the DER bytes inside a PEM certificate
+pub fn pem_digest(pem: &str) -> Result<String, GatewayError> {
+    let invalid = || GatewayError::ConfigError("Invalid PEM certificate".to_string());
+    let body = pem.trim()
+        .strip_prefix("-----BEGIN CERTIFICATE-----")
+        .and_then(|rest| rest.split("-----END CERTIFICATE-----").next())
+        .ok_or_else(invalid)?;
+    let encoded: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let der = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| invalid())?;
+    if der.is_empty() {
+        return Err(invalid());
+    }
+    Ok(hex::encode(Sha256::digest(&der)))
+}
+
+/// CA certificate IDs an `mtls-auth` plugin currently trusts
+pub fn trusted_ca_ids(plugin_config: &serde_json::Value) -> Vec<String> {
+    plugin_config["ca_certificates"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(|id| id.as_str().map(String::from)).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERT_A: &str = "-----BEGIN CERTIFICATE-----\naGVsbG8g\nZGVy\n-----END CERTIFICATE-----";
+    const CERT_B: &str = "-----BEGIN CERTIFICATE-----\nb3RoZXI=\n-----END CERTIFICATE-----";
+
+    fn revoked(subject_name: &str) -> RevokedCertificate {
+        RevokedCertificate {
+            subject_name: subject_name.to_string(),
+            reason: "key compromise".to_string(),
+            revoked_at: chrono::Utc::now(),
+            removed_credentials: 0,
+        }
+    }
+
+    fn scratch_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.json", name, uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_split_pem_bundle() {
+        let bundle = format!("# issuing CA\n{}\n\nsubject=intermediate\n{}\n-----BEGIN CERTIFICATE-----\ntruncated", CERT_A, CERT_B);
+        assert_eq!(split_pem_bundle(&bundle), vec![CERT_A.to_string(), CERT_B.to_string()]);
+        assert!(split_pem_bundle("no certificates here").is_empty());
+    }
+
+    #[test]
+    fn test_pem_digest() {
+        // SHA-256 of the DER bytes "hello der", whatever the line wrapping
+        let expected = "02bfa9e25620bbb46912d0e916bb4bc1aa5001e93652bbbb3b70d335be1347b9";
+        assert_eq!(pem_digest(CERT_A).unwrap(), expected);
+        assert_eq!(pem_digest(&format!("\n  {}\n", CERT_A.replace('\n', "\r\n"))).unwrap(), expected);
+        assert_ne!(pem_digest(CERT_B).unwrap(), expected);
+
+        assert!(pem_digest("aGVsbG8gZGVy").is_err());
+        assert!(pem_digest("-----BEGIN CERTIFICATE-----\n!!!\n-----END CERTIFICATE-----").is_err());
+        assert!(pem_digest("-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----").is_err());
+    }
+
+    #[test]
+    fn test_trusted_ca_ids() {
+        let config = serde_json::json!({ "ca_certificates": ["ca-1", 7, "ca-2"] });
+        assert_eq!(trusted_ca_ids(&config), vec!["ca-1".to_string(), "ca-2".to_string()]);
+        assert!(trusted_ca_ids(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_subjects_compare_case_insensitively() {
+        let manager = MtlsManager::new();
+        manager.revoke(revoked(" Client.Example.com ")).unwrap();
+        assert!(manager.is_revoked("client.example.com"));
+        assert!(manager.reinstate("CLIENT.EXAMPLE.COM").unwrap().is_some());
+        assert!(!manager.is_revoked("client.example.com"));
+        assert!(manager.reinstate("client.example.com").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_revocation_list_survives_restart() {
+        let path = scratch_file("mtls-revoked");
+        let manager = MtlsManager::persistent(&path).unwrap();
+        assert!(manager.revoked().is_empty());
+        manager.revoke(revoked("a.example.com")).unwrap();
+        manager.revoke(revoked("b.example.com")).unwrap();
+        manager.reinstate("a.example.com").unwrap();
+        drop(manager);
+
+        let restarted = MtlsManager::persistent(&path).unwrap();
+        assert!(restarted.is_revoked("b.example.com"));
+        assert!(!restarted.is_revoked("a.example.com"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_revocation_file_rejected() {
+        let path = scratch_file("mtls-corrupt");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(MtlsManager::persistent(&path), Err(GatewayError::RevocationListError(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+//! Redis cluster rate limit store
+//!
+//! Each check is one Lua script on one key, so it is atomic and needs a
+//! single round trip. Keys carry a `{hash tag}` so the bucket and window
+//! state of a limiter key land in the same slot. Scripts read the clock with
+//! `TIME`, giving every PoP the same view of time regardless of local skew
+//! (requires Redis 5+ for effect replication).
+
+use super::store::{Decision, RateLimitStore};
+use crate::GatewayError;
+use async_trait::async_trait;
+use redis::cluster::ClusterClient;
+use redis::cluster_async::ClusterConnection;
+use redis::Script;
+use std::time::Duration;
+
+const TOKEN_BUCKET: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local ts = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * rate / 1000)
+
+local allowed, retry = 0, 0
+if tokens >= cost then
+  tokens = tokens - cost
+  allowed = 1
+elseif cost > capacity then
+  retry = -1
+else
+  retry = math.ceil((cost - tokens) * 1000 / rate)
+end
+
+local full = math.ceil((capacity - tokens) * 1000 / rate)
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', math.max(now, ts))
+redis.call('PEXPIRE', KEYS[1], full + 1000)
+return {allowed, math.floor(tokens), retry, full}
+"#;
+
+const SLIDING_WINDOW: &str = r#"
+local limit = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+local start = now - (now % window)
+
+local state = redis.call('HMGET', KEYS[1], 'start', 'cur', 'prev')
+local s = tonumber(state[1]) or start
+local cur = tonumber(state[2]) or 0
+local prev = tonumber(state[3]) or 0
+if s ~= start then
+  if s + window == start then prev = cur else prev = 0 end
+  cur = 0
+end
+
+local elapsed = now - start
+local estimate = prev * (window - elapsed) / window + cur
+local allowed, retry = 0, 0
+if estimate + cost <= limit then
+  cur = cur + cost
+  estimate = estimate + cost
+  allowed = 1
+elseif prev > 0 and limit >= cur + cost then
+  local max_weight = (limit - cur - cost) / prev
+  retry = math.max(0, math.ceil(window * (1 - max_weight)) - elapsed)
+else
+  retry = window - elapsed
+end
+
+redis.call('HSET', KEYS[1], 'start', start, 'cur', cur, 'prev', prev)
+redis.call('PEXPIRE', KEYS[1], window * 2)
+return {allowed, math.max(0, math.floor(limit - estimate)), retry, window - elapsed}
+"#;
+
+/// Rate limit state shared by all PoPs through a Redis cluster
+pub struct RedisClusterStore {
+    connection: ClusterConnection,
+    token_bucket: Script,
+    sliding_window: Script,
+}
+
+impl RedisClusterStore {
+    /// Connect to a cluster, e.g. `["redis://10.0.0.1:6379", ...]`
+    pub async fn connect(nodes: Vec<String>) -> Result<Self, GatewayError> {
+        let client = ClusterClient::new(nodes).map_err(store_error)?;
+        let connection = client.get_async_connection().await.map_err(store_error)?;
+        Ok(Self {
+            connection,
+            token_bucket: Script::new(TOKEN_BUCKET),
+            sliding_window: Script::new(SLIDING_WINDOW),
+        })
+    }
+
+    async fn run(&self, script: &Script, key: String, args: (f64, f64, u64)) -> Result<Decision, GatewayError> {
+        let mut connection = self.connection.clone();
+        let reply: Vec<i64> = script
+            .key(key)
+            .arg(args.0)
+            .arg(args.1)
+            .arg(args.2)
+            .invoke_async(&mut connection)
+            .await
+            .map_err(store_error)?;
+
+        match reply[..] {
+            [allowed, remaining, retry, reset] => Ok(Decision {
+                allowed: allowed == 1,
+                remaining: remaining.max(0) as u64,
+                retry_after_ms: if retry < 0 { u64::MAX } else { retry as u64 },
+                reset_after_ms: reset.max(0) as u64,
+            }),
+            _ => Err(GatewayError::StoreError(format!("Unexpected script reply: {:?}", reply))),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisClusterStore {
+    async fn take_tokens(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill_per_sec: f64,
+        cost: u64,
+    ) -> Result<Decision, GatewayError> {
+        self.run(&self.token_bucket, bucket_key(key), (capacity as f64, refill_per_sec, cost)).await
+    }
+
+    async fn hit_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        cost: u64,
+    ) -> Result<Decision, GatewayError> {
+        let window_ms = (window.as_millis() as u64).max(1);
+        self.run(&self.sliding_window, window_key(key), (limit as f64, window_ms as f64, cost)).await
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), GatewayError> {
+        let mut connection = self.connection.clone();
+        redis::cmd("DEL")
+            .arg(bucket_key(key))
+            .arg(window_key(key))
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(store_error)
+    }
+}
+
+fn bucket_key(key: &str) -> String {
+    format!("{{rl:{}}}:tb", key)
+}
+
+fn window_key(key: &str) -> String {
+    format!("{{rl:{}}}:sw", key)
+}
+
+fn store_error(e: redis::RedisError) -> GatewayError {
+    GatewayError::StoreError(e.to_string())
+}
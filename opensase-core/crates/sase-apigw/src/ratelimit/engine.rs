@@ -0,0 +1,500 @@
+//! Rate limit enforcement engine
+//!
+//! Unlike [`RateLimiter`](super::RateLimiter), which mirrors Kong's plugin
+//! limits, the engine enforces limits itself and can be embedded by any
+//! service. Rules are keyed by consumer, client IP, route or globally, and
+//! state lives in a [`RateLimitStore`]: a Redis cluster for limits shared
+//! across PoPs, or process memory.
+//!
+//! When the shared store is unreachable the engine fails open onto local
+//! state by default, so each PoP keeps enforcing its own share of the limit
+//! instead of rejecting all traffic.
+
+use super::store::{Decision, MemoryStore, RateLimitStore};
+use super::RateLimitResult;
+use crate::GatewayError;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+// =============================================================================
+// Rules
+// =============================================================================
+
+/// What a rule counts requests by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitScope {
+    Consumer,
+    Ip,
+    Route,
+    Global,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAlgorithm {
+    /// Steady refill with bursts up to `limit + burst`
+    TokenBucket,
+    /// At most `limit` requests in any `period`
+    SlidingWindow,
+}
+
+/// One enforced limit
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LimitRule {
+    pub name: String,
+    pub scope: LimitScope,
+    pub algorithm: LimitAlgorithm,
+    /// Requests per period
+    pub limit: u64,
+    pub period_secs: u64,
+    /// Extra requests a token bucket absorbs at once; ignored by sliding
+    /// windows
+    #[serde(default)]
+    pub burst: u64,
+    /// Only apply on these routes; empty applies everywhere
+    #[serde(default)]
+    pub routes: Vec<String>,
+}
+
+impl LimitRule {
+    pub fn token_bucket(name: &str, scope: LimitScope, limit: u64, period_secs: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            scope,
+            algorithm: LimitAlgorithm::TokenBucket,
+            limit,
+            period_secs,
+            burst: 0,
+            routes: Vec::new(),
+        }
+    }
+
+    pub fn sliding_window(name: &str, scope: LimitScope, limit: u64, period_secs: u64) -> Self {
+        Self {
+            algorithm: LimitAlgorithm::SlidingWindow,
+            ..Self::token_bucket(name, scope, limit, period_secs)
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u64) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn for_routes(mut self, routes: Vec<String>) -> Self {
+        self.routes = routes;
+        self
+    }
+
+    fn validate(&self) -> Result<(), GatewayError> {
+        if self.name.is_empty() || self.name.contains(':') {
+            return Err(GatewayError::ConfigError(format!("Invalid rate limit rule name {:?}", self.name)));
+        }
+        if self.limit == 0 || self.period_secs == 0 {
+            return Err(GatewayError::ConfigError(format!(
+                "Rate limit rule {} needs a non-zero limit and period", self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Limit for one key that replaces the rule's own
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LimitOverride {
+    pub limit: u64,
+    pub burst: u64,
+}
+
+/// Identity of a request for limit keys
+#[derive(Clone, Debug, Default)]
+pub struct LimitRequest {
+    pub consumer_id: Option<String>,
+    pub client_ip: Option<String>,
+    pub route_id: Option<String>,
+}
+
+impl LimitRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_consumer(mut self, consumer_id: &str) -> Self {
+        self.consumer_id = Some(consumer_id.to_string());
+        self
+    }
+
+    pub fn with_client_ip(mut self, ip: &str) -> Self {
+        self.client_ip = Some(ip.to_string());
+        self
+    }
+
+    pub fn with_route(mut self, route_id: &str) -> Self {
+        self.route_id = Some(route_id.to_string());
+        self
+    }
+
+    fn key_for(&self, scope: LimitScope) -> Option<&str> {
+        match scope {
+            LimitScope::Consumer => self.consumer_id.as_deref(),
+            LimitScope::Ip => self.client_ip.as_deref(),
+            LimitScope::Route => self.route_id.as_deref(),
+            LimitScope::Global => Some("*"),
+        }
+    }
+}
+
+// =============================================================================
+// Engine
+// =============================================================================
+
+/// Result of a check, with the rule that decided it
+#[derive(Clone, Debug)]
+pub struct LimitOutcome {
+    pub result: RateLimitResult,
+    /// Rule that denied the request, or the tightest one that allowed it
+    pub rule: Option<String>,
+    /// Decided from local state because the shared store failed
+    pub degraded: bool,
+}
+
+pub struct RateLimitEngine {
+    rules: parking_lot::RwLock<Vec<LimitRule>>,
+    /// Keyed by `rule:key`
+    overrides: DashMap<String, LimitOverride>,
+    store: Arc<dyn RateLimitStore>,
+    /// Fallback when `store` fails
+    local: Arc<MemoryStore>,
+    fail_open: bool,
+    prefix: String,
+}
+
+impl RateLimitEngine {
+    pub fn new(store: Arc<dyn RateLimitStore>) -> Self {
+        Self {
+            rules: parking_lot::RwLock::new(Vec::new()),
+            overrides: DashMap::new(),
+            store,
+            local: Arc::new(MemoryStore::new()),
+            fail_open: true,
+            prefix: "osag".to_string(),
+        }
+    }
+
+    /// Engine with process-local state only
+    pub fn in_memory() -> Self {
+        let local = Arc::new(MemoryStore::new());
+        let mut engine = Self::new(local.clone());
+        engine.local = local;
+        engine
+    }
+
+    /// Namespace for store keys, so several engines can share one cluster
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Reject requests instead of falling back to local state when the
+    /// shared store fails
+    pub fn fail_closed(mut self) -> Self {
+        self.fail_open = false;
+        self
+    }
+
+    pub fn with_rule(self, rule: LimitRule) -> Result<Self, GatewayError> {
+        self.add_rule(rule)?;
+        Ok(self)
+    }
+
+    /// Add or replace a rule by name
+    pub fn add_rule(&self, rule: LimitRule) -> Result<(), GatewayError> {
+        rule.validate()?;
+        let mut rules = self.rules.write();
+        rules.retain(|r| r.name != rule.name);
+        rules.push(rule);
+        Ok(())
+    }
+
+    pub fn remove_rule(&self, name: &str) -> Option<LimitRule> {
+        let mut rules = self.rules.write();
+        let index = rules.iter().position(|r| r.name == name)?;
+        self.overrides.retain(|k, _| !k.starts_with(&format!("{}:", name)));
+        Some(rules.remove(index))
+    }
+
+    pub fn rules(&self) -> Vec<LimitRule> {
+        self.rules.read().clone()
+    }
+
+    /// Give one key (e.g. a premium consumer) its own limit under a rule
+    pub fn set_override(&self, rule: &str, key: &str, limit: LimitOverride) {
+        self.overrides.insert(format!("{}:{}", rule, key), limit);
+    }
+
+    pub fn clear_override(&self, rule: &str, key: &str) {
+        self.overrides.remove(&format!("{}:{}", rule, key));
+    }
+
+    /// Check and count one request
+    pub async fn check(&self, request: &LimitRequest) -> Result<LimitOutcome, GatewayError> {
+        self.check_cost(request, 1).await
+    }
+
+    /// Check and count a request weighing `cost` units against every
+    /// applicable rule. Rules are applied in order; a denial stops
+    /// evaluation, so earlier rules may already have counted the request.
+    pub async fn check_cost(&self, request: &LimitRequest, cost: u64) -> Result<LimitOutcome, GatewayError> {
+        let rules = self.rules.read().clone();
+        let mut tightest: Option<(LimitRule, u64, Decision)> = None;
+        let mut degraded = false;
+
+        for rule in rules {
+            if !rule.routes.is_empty()
+                && !request.route_id.as_ref().is_some_and(|r| rule.routes.contains(r))
+            {
+                continue;
+            }
+            let Some(key) = request.key_for(rule.scope) else {
+                continue;
+            };
+
+            let (limit, burst) = self.overrides
+                .get(&format!("{}:{}", rule.name, key))
+                .map(|o| (o.limit, o.burst))
+                .unwrap_or((rule.limit, rule.burst));
+            let store_key = format!("{}:{}:{}", self.prefix, rule.name, key);
+
+            let (decision, fell_back) = self.decide(&rule, &store_key, limit, burst, cost).await?;
+            degraded |= fell_back;
+
+            if !decision.allowed {
+                return Ok(LimitOutcome {
+                    result: to_result(limit, &decision),
+                    rule: Some(rule.name),
+                    degraded,
+                });
+            }
+            if tightest.as_ref().is_none_or(|(_, _, d)| d.remaining > decision.remaining) {
+                tightest = Some((rule, limit, decision));
+            }
+        }
+
+        Ok(match tightest {
+            Some((rule, limit, decision)) => LimitOutcome {
+                result: to_result(limit, &decision),
+                rule: Some(rule.name),
+                degraded,
+            },
+            None => LimitOutcome {
+                result: RateLimitResult {
+                    allowed: true,
+                    remaining: u32::MAX,
+                    limit: 0,
+                    reset_at: None,
+                    retry_after: None,
+                },
+                rule: None,
+                degraded,
+            },
+        })
+    }
+
+    async fn decide(
+        &self,
+        rule: &LimitRule,
+        key: &str,
+        limit: u64,
+        burst: u64,
+        cost: u64,
+    ) -> Result<(Decision, bool), GatewayError> {
+        match apply(self.store.as_ref(), rule, key, limit, burst, cost).await {
+            Ok(decision) => Ok((decision, false)),
+            Err(e) if self.fail_open => {
+                tracing::warn!("Rate limit store failed for {}, using local state: {}", key, e);
+                Ok((apply(self.local.as_ref(), rule, key, limit, burst, cost).await?, true))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Clear a key's state under a rule
+    pub async fn reset(&self, rule: &str, key: &str) -> Result<(), GatewayError> {
+        let store_key = format!("{}:{}:{}", self.prefix, rule, key);
+        self.local.reset(&store_key).await?;
+        self.store.reset(&store_key).await
+    }
+
+    /// Drop local fallback state idle for longer than `idle`
+    pub fn purge_local(&self, idle: Duration) {
+        self.local.purge_idle(idle);
+    }
+}
+
+async fn apply(
+    store: &dyn RateLimitStore,
+    rule: &LimitRule,
+    key: &str,
+    limit: u64,
+    burst: u64,
+    cost: u64,
+) -> Result<Decision, GatewayError> {
+    match rule.algorithm {
+        LimitAlgorithm::TokenBucket => {
+            let refill_per_sec = limit as f64 / rule.period_secs as f64;
+            store.take_tokens(key, limit + burst, refill_per_sec, cost).await
+        }
+        LimitAlgorithm::SlidingWindow => {
+            store.hit_window(key, limit, Duration::from_secs(rule.period_secs), cost).await
+        }
+    }
+}
+
+fn to_result(limit: u64, decision: &Decision) -> RateLimitResult {
+    let now = chrono::Utc::now().timestamp();
+    RateLimitResult {
+        allowed: decision.allowed,
+        remaining: decision.remaining.min(u32::MAX as u64) as u32,
+        limit: limit.min(u32::MAX as u64) as u32,
+        reset_at: Some(now + decision.reset_after_ms.div_ceil(1000) as i64),
+        retry_after: (!decision.allowed)
+            .then(|| decision.retry_after_ms.div_ceil(1000).min(u32::MAX as u64) as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Shared store that is always unreachable
+    struct DownStore;
+
+    #[async_trait]
+    impl RateLimitStore for DownStore {
+        async fn take_tokens(&self, _: &str, _: u64, _: f64, _: u64) -> Result<Decision, GatewayError> {
+            Err(GatewayError::StoreError("connection refused".to_string()))
+        }
+
+        async fn hit_window(&self, _: &str, _: u64, _: Duration, _: u64) -> Result<Decision, GatewayError> {
+            Err(GatewayError::StoreError("connection refused".to_string()))
+        }
+
+        async fn reset(&self, _: &str) -> Result<(), GatewayError> {
+            Err(GatewayError::StoreError("connection refused".to_string()))
+        }
+    }
+
+    fn consumer(id: &str) -> LimitRequest {
+        LimitRequest::new().with_consumer(id).with_client_ip("203.0.113.7").with_route("orders")
+    }
+
+    #[tokio::test]
+    async fn test_check_cost_counts_weight() {
+        let engine = RateLimitEngine::in_memory()
+            .with_rule(LimitRule::sliding_window("per-consumer", LimitScope::Consumer, 10, 3600))
+            .unwrap();
+
+        let outcome = engine.check_cost(&consumer("alice"), 4).await.unwrap();
+        assert!(outcome.result.allowed);
+        assert_eq!(outcome.result.remaining, 6);
+        assert_eq!(outcome.result.limit, 10);
+
+        let outcome = engine.check_cost(&consumer("alice"), 7).await.unwrap();
+        assert!(!outcome.result.allowed);
+        assert_eq!(outcome.rule.as_deref(), Some("per-consumer"));
+        assert!(outcome.result.retry_after.is_some());
+
+        // The rejected request was not counted, and keys are independent
+        assert!(engine.check_cost(&consumer("alice"), 6).await.unwrap().result.allowed);
+        assert!(engine.check_cost(&consumer("bob"), 10).await.unwrap().result.allowed);
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_burst() {
+        let engine = RateLimitEngine::in_memory()
+            .with_rule(LimitRule::token_bucket("ip", LimitScope::Ip, 5, 3600).with_burst(3))
+            .unwrap();
+
+        for _ in 0..8 {
+            assert!(engine.check(&consumer("alice")).await.unwrap().result.allowed);
+        }
+        let outcome = engine.check(&consumer("alice")).await.unwrap();
+        assert!(!outcome.result.allowed);
+        assert_eq!(outcome.result.limit, 5);
+    }
+
+    #[tokio::test]
+    async fn test_reports_tightest_rule() {
+        let engine = RateLimitEngine::in_memory()
+            .with_rule(LimitRule::token_bucket("per-consumer", LimitScope::Consumer, 100, 60))
+            .unwrap()
+            .with_rule(LimitRule::sliding_window("global", LimitScope::Global, 5, 3600))
+            .unwrap()
+            .with_rule(LimitRule::sliding_window("checkout", LimitScope::Route, 1, 3600).for_routes(vec!["checkout".to_string()]))
+            .unwrap();
+
+        let outcome = engine.check(&consumer("alice")).await.unwrap();
+        assert_eq!(outcome.rule.as_deref(), Some("global"));
+        assert_eq!(outcome.result.remaining, 4);
+
+        // The route rule only applies on its own routes
+        let checkout = consumer("alice").with_route("checkout");
+        assert_eq!(engine.check(&checkout).await.unwrap().rule.as_deref(), Some("checkout"));
+        let outcome = engine.check(&checkout).await.unwrap();
+        assert!(!outcome.result.allowed);
+        assert_eq!(outcome.rule.as_deref(), Some("checkout"));
+    }
+
+    #[tokio::test]
+    async fn test_override_and_missing_scope_key() {
+        let engine = RateLimitEngine::in_memory()
+            .with_rule(LimitRule::sliding_window("per-consumer", LimitScope::Consumer, 1, 3600))
+            .unwrap();
+        engine.set_override("per-consumer", "premium", LimitOverride { limit: 3, burst: 0 });
+
+        for _ in 0..3 {
+            assert!(engine.check(&consumer("premium")).await.unwrap().result.allowed);
+        }
+        assert!(!engine.check(&consumer("premium")).await.unwrap().result.allowed);
+
+        // Anonymous requests have no consumer key, so no rule applies
+        let outcome = engine.check(&LimitRequest::new().with_client_ip("203.0.113.7")).await.unwrap();
+        assert!(outcome.result.allowed);
+        assert_eq!(outcome.rule, None);
+
+        engine.remove_rule("per-consumer");
+        assert!(engine.check(&consumer("premium")).await.unwrap().rule.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_store_failure() {
+        let rule = LimitRule::sliding_window("global", LimitScope::Global, 1, 3600);
+
+        let open = RateLimitEngine::new(Arc::new(DownStore)).with_rule(rule.clone()).unwrap();
+        let outcome = open.check(&consumer("alice")).await.unwrap();
+        assert!(outcome.degraded);
+        assert!(outcome.result.allowed);
+        assert!(!open.check(&consumer("alice")).await.unwrap().result.allowed);
+
+        let closed = RateLimitEngine::new(Arc::new(DownStore)).fail_closed().with_rule(rule).unwrap();
+        assert!(matches!(closed.check(&consumer("alice")).await, Err(GatewayError::StoreError(_))));
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        let engine = RateLimitEngine::in_memory();
+        assert!(engine.add_rule(LimitRule::token_bucket("a:b", LimitScope::Global, 1, 1)).is_err());
+        assert!(engine.add_rule(LimitRule::token_bucket("zero", LimitScope::Global, 0, 1)).is_err());
+        assert!(engine.add_rule(LimitRule::sliding_window("no-period", LimitScope::Global, 1, 0)).is_err());
+
+        engine.add_rule(LimitRule::token_bucket("a", LimitScope::Global, 1, 1)).unwrap();
+        engine.add_rule(LimitRule::sliding_window("a", LimitScope::Ip, 2, 1)).unwrap();
+        let rules = engine.rules();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].algorithm, LimitAlgorithm::SlidingWindow);
+    }
+}
@@ -1,6 +1,19 @@
 //! Rate Limiting
 //!
 //! Token bucket, sliding window, and quota-based rate limiting.
+//!
+//! [`RateLimiter`] tracks the limits configured in Kong; [`RateLimitEngine`]
+//! enforces limits directly, optionally with state shared over Redis.
+
+mod engine;
+mod store;
+#[cfg(feature = "redis-cluster")]
+mod cluster;
+
+pub use engine::{LimitAlgorithm, LimitOutcome, LimitOverride, LimitRequest, LimitRule, LimitScope, RateLimitEngine};
+pub use store::{Decision, MemoryStore, RateLimitStore};
+#[cfg(feature = "redis-cluster")]
+pub use cluster::RedisClusterStore;
 
 use crate::{GatewayError, RateLimitConfig};
 use dashmap::DashMap;
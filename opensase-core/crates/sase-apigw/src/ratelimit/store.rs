@@ -0,0 +1,358 @@
+//! Rate limit state stores
+//!
+//! Both algorithms keep O(1) state per key so they map directly onto Redis
+//! hashes:
+//!
+//! - token bucket: token count and last refill time
+//! - sliding window: counts for the current and previous fixed windows,
+//!   with the previous one weighted by how much of it still overlaps the
+//!   sliding window
+//!
+//! [`MemoryStore`] and the Redis cluster store implement the same arithmetic,
+//! so a PoP falling back to local state enforces the same limits.
+
+use crate::GatewayError;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Outcome of one limit check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Requests still available right now
+    pub remaining: u64,
+    /// Wait before a denied request would be allowed
+    pub retry_after_ms: u64,
+    /// Time until the limit is fully replenished
+    pub reset_after_ms: u64,
+}
+
+/// Shared or local rate limit state
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Take `cost` tokens from a bucket holding at most `capacity`
+    async fn take_tokens(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill_per_sec: f64,
+        cost: u64,
+    ) -> Result<Decision, GatewayError>;
+
+    /// Count `cost` requests against a sliding window
+    async fn hit_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        cost: u64,
+    ) -> Result<Decision, GatewayError>;
+
+    /// Forget all state for a key
+    async fn reset(&self, key: &str) -> Result<(), GatewayError>;
+}
+
+// =============================================================================
+// In-memory Store
+// =============================================================================
+
+struct BucketState {
+    tokens: f64,
+    updated_ms: u64,
+}
+
+struct WindowState {
+    start_ms: u64,
+    current: u64,
+    previous: u64,
+}
+
+/// Process-local state; exact within one PoP
+pub struct MemoryStore {
+    buckets: DashMap<String, BucketState>,
+    windows: DashMap<String, WindowState>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self {
+            buckets: DashMap::new(),
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Drop state untouched for longer than `idle`
+    pub fn purge_idle(&self, idle: Duration) {
+        let cutoff = now_ms().saturating_sub(idle.as_millis() as u64);
+        self.buckets.retain(|_, b| b.updated_ms >= cutoff);
+        self.windows.retain(|_, w| w.start_ms >= cutoff);
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.len() + self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take_tokens(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill_per_sec: f64,
+        cost: u64,
+    ) -> Result<Decision, GatewayError> {
+        let now = now_ms();
+        let mut state = self.buckets.entry(key.to_string()).or_insert_with(|| BucketState {
+            tokens: capacity as f64,
+            updated_ms: now,
+        });
+
+        let (tokens, decision) = token_bucket(state.tokens, state.updated_ms, now, capacity, refill_per_sec, cost);
+        state.tokens = tokens;
+        state.updated_ms = now.max(state.updated_ms);
+        Ok(decision)
+    }
+
+    async fn hit_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        cost: u64,
+    ) -> Result<Decision, GatewayError> {
+        let now = now_ms();
+        let window_ms = (window.as_millis() as u64).max(1);
+        let mut state = self.windows.entry(key.to_string()).or_insert_with(|| WindowState {
+            start_ms: now - now % window_ms,
+            current: 0,
+            previous: 0,
+        });
+
+        let (start, current, previous, decision) =
+            sliding_window(state.start_ms, state.current, state.previous, now, limit, window_ms, cost);
+        state.start_ms = start;
+        state.current = current;
+        state.previous = previous;
+        Ok(decision)
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), GatewayError> {
+        self.buckets.remove(key);
+        self.windows.remove(key);
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Algorithms
+// =============================================================================
+
+/// Refill and take from a bucket; returns the new token count
+pub(crate) fn token_bucket(
+    tokens: f64,
+    updated_ms: u64,
+    now_ms: u64,
+    capacity: u64,
+    refill_per_sec: f64,
+    cost: u64,
+) -> (f64, Decision) {
+    let capacity = capacity as f64;
+    let cost_f = cost as f64;
+    let elapsed = now_ms.saturating_sub(updated_ms) as f64;
+    let mut tokens = (tokens + elapsed * refill_per_sec / 1000.0).min(capacity);
+
+    let (allowed, retry_after_ms) = if tokens >= cost_f {
+        tokens -= cost_f;
+        (true, 0)
+    } else if cost_f > capacity {
+        // Can never be satisfied
+        (false, u64::MAX)
+    } else {
+        (false, ((cost_f - tokens) * 1000.0 / refill_per_sec).ceil() as u64)
+    };
+
+    let decision = Decision {
+        allowed,
+        remaining: tokens.floor() as u64,
+        retry_after_ms,
+        reset_after_ms: ((capacity - tokens) * 1000.0 / refill_per_sec).ceil() as u64,
+    };
+    (tokens, decision)
+}
+
+/// Weighted two-window count; returns the new (start, current, previous)
+pub(crate) fn sliding_window(
+    start_ms: u64,
+    current: u64,
+    previous: u64,
+    now_ms: u64,
+    limit: u64,
+    window_ms: u64,
+    cost: u64,
+) -> (u64, u64, u64, Decision) {
+    let window_start = now_ms - now_ms % window_ms;
+    let (mut current, previous) = if start_ms == window_start {
+        (current, previous)
+    } else if start_ms + window_ms == window_start {
+        (0, current)
+    } else {
+        (0, 0)
+    };
+
+    let elapsed = now_ms - window_start;
+    let weight = (window_ms - elapsed) as f64 / window_ms as f64;
+    let mut estimate = previous as f64 * weight + current as f64;
+
+    let (allowed, retry_after_ms) = if estimate + cost as f64 <= limit as f64 {
+        current += cost;
+        estimate += cost as f64;
+        (true, 0)
+    } else if previous > 0 && limit >= current + cost {
+        // Wait until enough of the previous window has slid out
+        let max_weight = (limit - current - cost) as f64 / previous as f64;
+        let at = (window_ms as f64 * (1.0 - max_weight)).ceil() as u64;
+        (false, at.saturating_sub(elapsed))
+    } else {
+        (false, window_ms - elapsed)
+    };
+
+    let decision = Decision {
+        allowed,
+        remaining: (limit as f64 - estimate).max(0.0).floor() as u64,
+        retry_after_ms,
+        reset_after_ms: window_ms - elapsed,
+    };
+    (window_start, current, previous, decision)
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_takes_and_refills() {
+        let (tokens, decision) = token_bucket(10.0, 0, 0, 10, 1.0, 1);
+        assert_eq!(tokens, 9.0);
+        assert_eq!(decision, Decision { allowed: true, remaining: 9, retry_after_ms: 0, reset_after_ms: 1000 });
+
+        // 2.5 s at 2 tokens/s refills 5
+        let (tokens, decision) = token_bucket(0.0, 1_000, 3_500, 10, 2.0, 1);
+        assert_eq!(tokens, 4.0);
+        assert!(decision.allowed);
+
+        // Refill never exceeds capacity
+        let (tokens, decision) = token_bucket(0.0, 0, 100_000, 10, 1.0, 1);
+        assert_eq!(tokens, 9.0);
+        assert_eq!(decision.remaining, 9);
+
+        // A clock step backwards refills nothing
+        let (tokens, _) = token_bucket(5.0, 2_000, 1_000, 10, 1.0, 1);
+        assert_eq!(tokens, 4.0);
+    }
+
+    #[test]
+    fn test_token_bucket_burst_then_deny() {
+        let mut tokens = 15.0;
+        for _ in 0..15 {
+            let (left, decision) = token_bucket(tokens, 0, 0, 15, 1.0, 1);
+            assert!(decision.allowed);
+            tokens = left;
+        }
+        let (left, decision) = token_bucket(tokens, 0, 0, 15, 1.0, 1);
+        assert_eq!(left, 0.0);
+        assert_eq!(decision, Decision { allowed: false, remaining: 0, retry_after_ms: 1000, reset_after_ms: 15_000 });
+    }
+
+    #[test]
+    fn test_token_bucket_cost() {
+        let (tokens, decision) = token_bucket(10.0, 0, 0, 10, 1.0, 4);
+        assert_eq!((tokens, decision.allowed, decision.remaining), (6.0, true, 6));
+
+        // Not enough tokens: nothing is taken and the wait covers the shortfall
+        let (tokens, decision) = token_bucket(3.0, 0, 0, 10, 2.0, 5);
+        assert_eq!(tokens, 3.0);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_ms, 1000);
+
+        // More than the bucket can ever hold
+        let (_, decision) = token_bucket(10.0, 0, 0, 10, 1.0, 11);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_ms, u64::MAX);
+    }
+
+    #[test]
+    fn test_sliding_window_fills_within_window() {
+        let (start, current, previous, decision) = sliding_window(0, 0, 0, 0, 10, 1000, 1);
+        assert_eq!((start, current, previous), (0, 1, 0));
+        assert_eq!(decision, Decision { allowed: true, remaining: 9, retry_after_ms: 0, reset_after_ms: 1000 });
+
+        let (_, current, _, decision) = sliding_window(0, 10, 0, 500, 10, 1000, 1);
+        assert_eq!(current, 10);
+        assert_eq!(decision, Decision { allowed: false, remaining: 0, retry_after_ms: 500, reset_after_ms: 500 });
+    }
+
+    #[test]
+    fn test_sliding_window_rollover() {
+        // Half-way into the next window, half of the previous count remains
+        let (start, current, previous, decision) = sliding_window(0, 10, 0, 1500, 10, 1000, 1);
+        assert_eq!((start, current, previous), (1000, 1, 10));
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 4);
+
+        // Denied until enough of the previous window has slid out
+        let (_, current, _, decision) = sliding_window(1000, 5, 10, 1200, 10, 1000, 1);
+        assert_eq!(current, 5);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_ms, 400);
+
+        // A gap of more than one window forgets both counts
+        let (start, current, previous, decision) = sliding_window(0, 10, 10, 5250, 10, 1000, 1);
+        assert_eq!((start, current, previous), (5000, 1, 0));
+        assert_eq!(decision.remaining, 9);
+    }
+
+    #[test]
+    fn test_sliding_window_cost() {
+        let (_, current, _, decision) = sliding_window(0, 7, 0, 100, 10, 1000, 3);
+        assert_eq!((current, decision.allowed, decision.remaining), (10, true, 0));
+
+        let (_, current, _, decision) = sliding_window(0, 8, 0, 100, 10, 1000, 3);
+        assert_eq!(current, 8);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_ms, 900);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_reset_and_purge() {
+        let store = MemoryStore::new();
+        assert!(store.take_tokens("a", 1, 0.001, 1).await.unwrap().allowed);
+        assert!(!store.take_tokens("a", 1, 0.001, 1).await.unwrap().allowed);
+        assert!(store.hit_window("b", 5, Duration::from_secs(3600), 1).await.unwrap().allowed);
+        assert_eq!(store.len(), 2);
+
+        store.reset("a").await.unwrap();
+        assert!(store.take_tokens("a", 1, 0.001, 1).await.unwrap().allowed);
+
+        store.purge_idle(Duration::from_secs(7200));
+        assert_eq!(store.len(), 2);
+    }
+}
@@ -137,6 +137,27 @@ impl CostOptimizer {
         self.budget = Some(budget);
     }
 
+    /// Monthly budget, if set
+    pub fn budget(&self) -> Option<Decimal> {
+        self.budget
+    }
+
+    /// Cheapest provider and monthly cost for carrying additional traffic
+    /// between two PoPs on top of the demand already on that path
+    pub fn incremental_transport_cost(&self, src_pop: &str, dst_pop: &str, additional_mbps: u32) -> (BackboneProvider, Decimal) {
+        let current = self.get_link_demand(src_pop, dst_pop);
+        let added = self.round_to_increment(current + additional_mbps) - self.round_to_increment(current);
+
+        [
+            (BackboneProvider::Megaport, &self.megaport_pricing),
+            (BackboneProvider::PacketFabric, &self.packetfabric_pricing),
+        ]
+        .into_iter()
+        .map(|(provider, pricing)| (provider, pricing.cost_per_mbps * Decimal::from(added)))
+        .min_by_key(|(_, cost)| *cost)
+        .unwrap_or((BackboneProvider::PacketFabric, Decimal::ZERO))
+    }
+
     /// Add traffic demand
     pub fn add_traffic_demand(&mut self, demand: TrafficDemand) {
         self.traffic_demands.push(demand);
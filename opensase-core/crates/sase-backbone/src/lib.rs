@@ -101,6 +101,21 @@ pub struct VxcEndpoint {
     pub vlan_id: u16,
 }
 
+/// Provisioned backbone link between two PoPs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackboneLink {
+    pub id: String,
+    pub name: String,
+    pub provider: BackboneProvider,
+    pub a_end: VxcEndpoint,
+    pub z_end: VxcEndpoint,
+    pub bandwidth_mbps: u32,
+    pub burst_mbps: Option<u32>,
+    pub status: VxcStatus,
+    pub latency_ms: Option<f32>,
+    pub monthly_cost: rust_decimal::Decimal,
+}
+
 /// Backbone configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackboneConfig {
@@ -9,10 +9,14 @@ use rust_decimal_macros::dec;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::invoicing::Invoice;
+use crate::subscriptions::{Subscription, SubscriptionStatus};
+
 /// Credit manager
 pub struct CreditManager {
     credits: Arc<RwLock<HashMap<Uuid, Credit>>>,
     promo_codes: Arc<RwLock<HashMap<String, PromoCode>>>,
+    redemptions: Arc<RwLock<HashMap<Uuid, PromoRedemption>>>,
 }
 
 impl CreditManager {
@@ -20,6 +24,7 @@ impl CreditManager {
         Self {
             credits: Arc::new(RwLock::new(HashMap::new())),
             promo_codes: Arc::new(RwLock::new(HashMap::new())),
+            redemptions: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -118,36 +123,57 @@ impl CreditManager {
         (referrer_id, referred_credit_id)
     }
 
+    /// Carry a net proration credit from an invoice to the tenant's balance
+    pub fn issue_proration_credit(&self, tenant_id: Uuid, invoice_number: &str, amount: Decimal) -> Uuid {
+        let credit = Credit {
+            id: Uuid::new_v4(),
+            tenant_id,
+            credit_type: CreditType::Proration,
+            description: format!("Plan change credit from {}", invoice_number),
+            original_amount: amount,
+            remaining_amount: amount,
+            used_amount: dec!(0),
+            expires_at: None,
+            created_at: Utc::now(),
+        };
+        self.add_credit(credit)
+    }
+
     /// Create promo code
     pub fn create_promo_code(&self, code: &str, promo: PromoCode) {
         self.promo_codes.write().insert(code.to_uppercase(), promo);
     }
 
+    /// Get promo code
+    pub fn get_promo_code(&self, code: &str) -> Option<PromoCode> {
+        self.promo_codes.read().get(&code.to_uppercase()).cloned()
+    }
+
     /// Validate and apply promo code
+    ///
+    /// Fixed amounts become a promotion credit; percentages and trials are
+    /// returned for the caller to apply. Use
+    /// [`CreditManager::redeem_promo_code`] to discount a subscription's
+    /// invoices instead.
     pub fn apply_promo_code(&self, code: &str, tenant_id: Uuid) -> Result<PromoResult, CreditError> {
+        let key = code.to_uppercase();
         let discount_type = {
             let mut codes = self.promo_codes.write();
-            let promo = codes.get_mut(&code.to_uppercase())
+            let promo = codes.get_mut(&key)
                 .ok_or(CreditError::InvalidCode)?;
 
-            // Check if expired
-            if let Some(exp) = promo.expires_at {
-                if exp < Utc::now() {
-                    return Err(CreditError::ExpiredCode);
-                }
-            }
-
-            // Check redemption limit
-            if let Some(limit) = promo.max_redemptions {
-                if promo.redemptions >= limit {
-                    return Err(CreditError::CodeLimitReached);
-                }
-            }
+            let mut redemptions = self.redemptions.write();
+            check_promo(&key, promo, tenant_id, &redemptions, Utc::now())?;
 
             // Apply the promo
             promo.redemptions += 1;
+            let redemption = PromoRedemption::new(&key, promo, tenant_id, None, Utc::now());
+            redemptions.insert(redemption.id, PromoRedemption {
+                status: RedemptionStatus::Completed,
+                ..redemption
+            });
             promo.discount_type.clone()
-        }; // Lock dropped here
+        }; // Locks dropped here
 
         match &discount_type {
             DiscountType::Percentage(pct) => {
@@ -174,6 +200,135 @@ impl CreditManager {
             }
         }
     }
+
+    /// Redeem a promo code against a subscription
+    ///
+    /// The discount is billed as a line on the subscription's next invoice,
+    /// and on later ones for repeating and forever codes. `now` is the
+    /// tenant's billing clock.
+    pub fn redeem_promo_code(
+        &self,
+        code: &str,
+        subscription: &Subscription,
+        now: DateTime<Utc>,
+    ) -> Result<PromoRedemption, CreditError> {
+        let key = code.to_uppercase();
+        let mut codes = self.promo_codes.write();
+        let promo = codes.get_mut(&key).ok_or(CreditError::InvalidCode)?;
+
+        if !matches!(promo.discount_type, DiscountType::Percentage(_) | DiscountType::FixedAmount(_)) {
+            return Err(CreditError::NotApplicable("code carries no invoice discount".into()));
+        }
+        if !matches!(subscription.status, SubscriptionStatus::Active | SubscriptionStatus::Trialing) {
+            return Err(CreditError::NotApplicable("subscription is not active".into()));
+        }
+        if !promo.plans.is_empty() && !promo.plans.contains(&subscription.plan_id) {
+            return Err(CreditError::NotApplicable(format!("code does not apply to the {} plan", subscription.plan_id)));
+        }
+
+        let mut redemptions = self.redemptions.write();
+        check_promo(&key, promo, subscription.tenant_id, &redemptions, now)?;
+
+        // Stacking: an exclusive discount is the only one on the subscription
+        let active: Vec<&PromoRedemption> = redemptions.values()
+            .filter(|r| r.subscription_id == Some(subscription.id) && r.is_active())
+            .collect();
+        if active.iter().any(|r| r.code == key) {
+            return Err(CreditError::AlreadyRedeemed);
+        }
+        if !active.is_empty() && (!promo.stackable || active.iter().any(|r| !r.stackable)) {
+            return Err(CreditError::NotStackable);
+        }
+
+        promo.redemptions += 1;
+        let redemption = PromoRedemption::new(&key, promo, subscription.tenant_id, Some(subscription.id), now);
+        redemptions.insert(redemption.id, redemption.clone());
+        Ok(redemption)
+    }
+
+    /// Discounts to bill on a subscription's next invoice, oldest first
+    pub fn pending_discounts(&self, subscription_id: Uuid) -> Vec<PromoRedemption> {
+        let mut discounts: Vec<_> = self.redemptions.read()
+            .values()
+            .filter(|r| r.subscription_id == Some(subscription_id) && r.is_active())
+            .cloned()
+            .collect();
+        discounts.sort_by_key(|r| r.redeemed_at);
+        discounts
+    }
+
+    /// Get redemptions for tenant
+    pub fn get_redemptions(&self, tenant_id: Uuid) -> Vec<PromoRedemption> {
+        self.redemptions.read()
+            .values()
+            .filter(|r| r.tenant_id == tenant_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Count an invoice's discount lines against their redemptions, so
+    /// one-off and repeating discounts run out
+    pub fn record_discounts(&self, invoice: &Invoice) {
+        let mut redemptions = self.redemptions.write();
+        for applied in &invoice.discount_details {
+            if let Some(redemption) = redemptions.get_mut(&applied.redemption_id) {
+                redemption.cycles_applied += 1;
+                redemption.amount_applied += applied.amount;
+                let cycles = match redemption.duration {
+                    PromoDuration::Once => Some(1),
+                    PromoDuration::Repeating(cycles) => Some(cycles),
+                    PromoDuration::Forever => None,
+                };
+                if cycles.is_some_and(|c| redemption.cycles_applied >= c) {
+                    redemption.status = RedemptionStatus::Completed;
+                }
+            }
+        }
+    }
+
+    /// Stop a redemption's discount on future invoices
+    pub fn revoke_redemption(&self, id: Uuid) -> Result<PromoRedemption, CreditError> {
+        let mut redemptions = self.redemptions.write();
+        let redemption = redemptions.get_mut(&id).ok_or(CreditError::NotFound)?;
+        redemption.status = RedemptionStatus::Revoked;
+        Ok(redemption.clone())
+    }
+}
+
+/// Validity window and usage limits of a promo code
+fn check_promo(
+    key: &str,
+    promo: &PromoCode,
+    tenant_id: Uuid,
+    redemptions: &HashMap<Uuid, PromoRedemption>,
+    now: DateTime<Utc>,
+) -> Result<(), CreditError> {
+    if let Some(starts) = promo.starts_at {
+        if now < starts {
+            return Err(CreditError::NotYetValid);
+        }
+    }
+    if let Some(exp) = promo.expires_at {
+        if exp < now {
+            return Err(CreditError::ExpiredCode);
+        }
+    }
+
+    if let Some(limit) = promo.max_redemptions {
+        if promo.redemptions >= limit {
+            return Err(CreditError::CodeLimitReached);
+        }
+    }
+    if let Some(limit) = promo.max_per_tenant {
+        let used = redemptions.values()
+            .filter(|r| r.code == key && r.tenant_id == tenant_id)
+            .count();
+        if used >= limit as usize {
+            return Err(CreditError::CodeLimitReached);
+        }
+    }
+
+    Ok(())
 }
 
 impl Default for CreditManager {
@@ -208,6 +363,8 @@ pub enum CreditType {
     Compensation,
     Partner,
     Promotion,
+    /// Plan downgrade credit carried over from an invoice
+    Proration,
 }
 
 /// Promo code
@@ -220,6 +377,73 @@ pub struct PromoCode {
     pub redemptions: u32,
     pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    /// Not redeemable before this time
+    #[serde(default)]
+    pub starts_at: Option<DateTime<Utc>>,
+    /// Redemptions allowed per tenant
+    #[serde(default)]
+    pub max_per_tenant: Option<u32>,
+    /// Invoices a subscription discount applies to
+    #[serde(default)]
+    pub duration: PromoDuration,
+    /// May be combined with other stackable discounts on a subscription
+    #[serde(default)]
+    pub stackable: bool,
+    /// Plans the code applies to; empty for any plan
+    #[serde(default)]
+    pub plans: Vec<String>,
+}
+
+impl PromoCode {
+    /// Single-use-per-tenant code with no window or global limit
+    pub fn new(code: &str, description: &str, discount_type: DiscountType) -> Self {
+        Self {
+            code: code.to_uppercase(),
+            description: description.into(),
+            discount_type,
+            max_redemptions: None,
+            redemptions: 0,
+            expires_at: None,
+            created_at: Utc::now(),
+            starts_at: None,
+            max_per_tenant: Some(1),
+            duration: PromoDuration::Once,
+            stackable: false,
+            plans: Vec::new(),
+        }
+    }
+
+    /// Redeemable from `starts_at` until `expires_at`
+    pub fn with_window(mut self, starts_at: Option<DateTime<Utc>>, expires_at: Option<DateTime<Utc>>) -> Self {
+        self.starts_at = starts_at;
+        self.expires_at = expires_at;
+        self
+    }
+
+    /// Limit total and per-tenant redemptions
+    pub fn with_limits(mut self, max_redemptions: Option<u32>, max_per_tenant: Option<u32>) -> Self {
+        self.max_redemptions = max_redemptions;
+        self.max_per_tenant = max_per_tenant;
+        self
+    }
+
+    /// Apply to this many invoices
+    pub fn with_duration(mut self, duration: PromoDuration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Allow combining with other stackable discounts
+    pub fn with_stacking(mut self, stackable: bool) -> Self {
+        self.stackable = stackable;
+        self
+    }
+
+    /// Restrict to plans
+    pub fn with_plans(mut self, plans: Vec<String>) -> Self {
+        self.plans = plans;
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -229,6 +453,98 @@ pub enum DiscountType {
     FreeTrial(u32), // days
 }
 
+/// How many invoices a subscription discount applies to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromoDuration {
+    /// Next invoice only
+    #[default]
+    Once,
+    /// Next N invoices
+    Repeating(u32),
+    /// Every invoice while the subscription lasts
+    Forever,
+}
+
+/// Promo code redeemed by a tenant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoRedemption {
+    /// Redemption ID
+    pub id: Uuid,
+    /// Code redeemed (upper case)
+    pub code: String,
+    /// Redeeming tenant
+    pub tenant_id: Uuid,
+    /// Subscription discounted, when redeemed against one
+    pub subscription_id: Option<Uuid>,
+    /// Discount, as of redemption
+    pub discount_type: DiscountType,
+    /// Invoices the discount applies to, as of redemption
+    pub duration: PromoDuration,
+    /// Combines with other stackable discounts
+    pub stackable: bool,
+    /// Invoices discounted so far
+    pub cycles_applied: u32,
+    /// Total discounted so far
+    pub amount_applied: Decimal,
+    /// Status
+    pub status: RedemptionStatus,
+    /// When the code was redeemed
+    pub redeemed_at: DateTime<Utc>,
+}
+
+impl PromoRedemption {
+    fn new(code: &str, promo: &PromoCode, tenant_id: Uuid, subscription_id: Option<Uuid>, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            code: code.into(),
+            tenant_id,
+            subscription_id,
+            discount_type: promo.discount_type.clone(),
+            duration: promo.duration,
+            stackable: promo.stackable,
+            cycles_applied: 0,
+            amount_applied: dec!(0),
+            status: RedemptionStatus::Active,
+            redeemed_at: now,
+        }
+    }
+
+    /// Still discounting invoices
+    pub fn is_active(&self) -> bool {
+        self.status == RedemptionStatus::Active
+    }
+
+    /// Discount on an invoice amount; fixed discounts do not carry over
+    /// what the invoice could not absorb
+    pub fn discount_on(&self, amount: Decimal) -> Decimal {
+        let discount = match &self.discount_type {
+            DiscountType::Percentage(pct) => (amount * (*pct).clamp(dec!(0), dec!(100)) / dec!(100)).round_dp(2),
+            DiscountType::FixedAmount(amt) => (*amt).max(dec!(0)),
+            DiscountType::FreeTrial(_) => dec!(0),
+        };
+        discount.min(amount.max(dec!(0)))
+    }
+
+    /// Invoice line description
+    pub fn description(&self) -> String {
+        match &self.discount_type {
+            DiscountType::Percentage(pct) => format!("Promo {} ({}% off)", self.code, pct.normalize()),
+            _ => format!("Promo {}", self.code),
+        }
+    }
+}
+
+/// Promo redemption lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedemptionStatus {
+    /// Discounting upcoming invoices
+    Active,
+    /// Applied to every invoice it covers
+    Completed,
+    /// Withdrawn before it ran out
+    Revoked,
+}
+
 /// Promo result
 #[derive(Debug, Clone)]
 pub enum PromoResult {
@@ -240,11 +556,25 @@ pub enum PromoResult {
 /// Credit error
 #[derive(Debug, Clone)]
 pub enum CreditError {
+    /// Credit or redemption does not exist
     NotFound,
+    /// Credit has less left than requested
     InsufficientBalance,
+    /// No such promo code
     InvalidCode,
+    /// Promo code's window has ended
     ExpiredCode,
+    /// Promo code redeemed its maximum number of times, overall or by this
+    /// tenant
     CodeLimitReached,
+    /// Promo code's window has not started
+    NotYetValid,
+    /// Subscription already holds this code
+    AlreadyRedeemed,
+    /// Code and the subscription's active discounts cannot be combined
+    NotStackable,
+    /// Code cannot discount this subscription (reason given)
+    NotApplicable(String),
 }
 
 impl std::fmt::Display for CreditError {
@@ -255,6 +585,10 @@ impl std::fmt::Display for CreditError {
             Self::InvalidCode => write!(f, "Invalid promo code"),
             Self::ExpiredCode => write!(f, "Promo code expired"),
             Self::CodeLimitReached => write!(f, "Promo code redemption limit reached"),
+            Self::NotYetValid => write!(f, "Promo code not yet valid"),
+            Self::AlreadyRedeemed => write!(f, "Promo code already redeemed on this subscription"),
+            Self::NotStackable => write!(f, "Promo code cannot be combined with the subscription's other discounts"),
+            Self::NotApplicable(reason) => write!(f, "Promo code not applicable: {}", reason),
         }
     }
 }
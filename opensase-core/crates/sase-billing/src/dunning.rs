@@ -0,0 +1,581 @@
+//! Dunning
+//!
+//! Tracks failed charges per tenant, schedules retries, sends reminder
+//! notices and decides when an account moves from its grace period to
+//! suspension or cancellation. Every transition is published as a
+//! [`DunningEvent`] so SOC and CRM integrations can follow delinquent
+//! accounts without polling billing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::broadcast;
+
+/// Dunning policy
+#[derive(Debug, Clone)]
+pub struct DunningConfig {
+    /// Delay before each retry, measured from the failure that preceded it.
+    /// The last entry repeats if `max_failures` exceeds the schedule.
+    pub retry_schedule: Vec<Duration>,
+    /// Service continues for this long after the first failure
+    pub grace_period: Duration,
+    /// Failed attempts (initial charge included) before the final action
+    pub max_failures: u32,
+    /// What happens when retries are exhausted or the grace period ends
+    pub final_action: DunningAction,
+}
+
+impl Default for DunningConfig {
+    fn default() -> Self {
+        Self {
+            retry_schedule: vec![
+                Duration::days(1),
+                Duration::days(3),
+                Duration::days(5),
+                Duration::days(7),
+            ],
+            grace_period: Duration::days(14),
+            max_failures: 4,
+            final_action: DunningAction::Suspend,
+        }
+    }
+}
+
+impl DunningConfig {
+    /// Delay before the retry following failure number `attempts`
+    pub fn retry_delay(&self, attempts: u32) -> Duration {
+        let idx = attempts.saturating_sub(1) as usize;
+        self.retry_schedule.get(idx)
+            .or(self.retry_schedule.last())
+            .copied()
+            .unwrap_or_else(|| Duration::days(1))
+    }
+}
+
+/// Action taken on an account once dunning is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DunningAction {
+    /// Suspend service; paying the balance restores it
+    Suspend,
+    /// Cancel the subscription
+    Cancel,
+}
+
+/// Dunning state for a tenant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningState {
+    /// Tenant
+    pub tenant_id: Uuid,
+    /// Invoice being collected
+    pub invoice_id: Uuid,
+    /// Amount outstanding
+    pub amount: Decimal,
+    /// Failed attempts so far
+    pub attempts: u32,
+    /// First failure
+    pub started_at: DateTime<Utc>,
+    /// Next retry (None once exhausted)
+    pub next_attempt: Option<DateTime<Utc>>,
+    /// End of the grace period
+    pub grace_until: DateTime<Utc>,
+    /// Last decline reason
+    pub last_error: Option<String>,
+    /// Reminder notices sent
+    pub emails_sent: Vec<DunningEmail>,
+    /// Status
+    pub status: DunningStatus,
+}
+
+/// Dunning status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DunningStatus {
+    /// Retries pending
+    Active,
+    /// Retries exhausted; final action applied
+    Exhausted,
+    /// Balance paid
+    Resolved,
+}
+
+/// Notice sent to the tenant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningEmail {
+    /// Notice type
+    pub email_type: DunningEmailType,
+    /// When it was sent
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Dunning notice type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DunningEmailType {
+    /// First failed charge
+    FirstReminder,
+    /// Second failed charge
+    SecondReminder,
+    /// Last retry before the final action
+    FinalWarning,
+    /// Account suspended
+    AccountSuspension,
+    /// Subscription canceled for non-payment
+    AccountCancellation,
+    /// Payment recovered
+    PaymentRecovered,
+}
+
+/// Dunning lifecycle event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DunningEvent {
+    /// A charge was declined
+    PaymentFailed {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Invoice
+        invoice_id: Uuid,
+        /// Amount
+        amount: Decimal,
+        /// Failure number
+        attempt: u32,
+        /// Decline reason
+        reason: String,
+        /// When
+        at: DateTime<Utc>,
+    },
+    /// First failure: the account is past due until `grace_until`
+    GracePeriodStarted {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Invoice
+        invoice_id: Uuid,
+        /// End of grace period
+        grace_until: DateTime<Utc>,
+    },
+    /// Next automatic retry
+    RetryScheduled {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Invoice
+        invoice_id: Uuid,
+        /// Retry number
+        attempt: u32,
+        /// When the retry runs
+        retry_at: DateTime<Utc>,
+    },
+    /// Reminder notice sent
+    NoticeSent {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Notice type
+        email_type: DunningEmailType,
+    },
+    /// Outstanding balance paid
+    Recovered {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Invoice
+        invoice_id: Uuid,
+        /// Failed attempts before recovery
+        attempts: u32,
+        /// When
+        at: DateTime<Utc>,
+    },
+    /// Service suspended for non-payment
+    Suspended {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Invoice
+        invoice_id: Uuid,
+        /// When
+        at: DateTime<Utc>,
+    },
+    /// Subscription canceled for non-payment
+    Canceled {
+        /// Tenant
+        tenant_id: Uuid,
+        /// Invoice
+        invoice_id: Uuid,
+        /// When
+        at: DateTime<Utc>,
+    },
+}
+
+impl DunningEvent {
+    /// Tenant the event concerns
+    pub fn tenant_id(&self) -> Uuid {
+        match self {
+            Self::PaymentFailed { tenant_id, .. }
+            | Self::GracePeriodStarted { tenant_id, .. }
+            | Self::RetryScheduled { tenant_id, .. }
+            | Self::NoticeSent { tenant_id, .. }
+            | Self::Recovered { tenant_id, .. }
+            | Self::Suspended { tenant_id, .. }
+            | Self::Canceled { tenant_id, .. } => *tenant_id,
+        }
+    }
+}
+
+/// Hook for delivering dunning notices (email, webhook, CRM task)
+pub trait DunningNotifier: Send + Sync {
+    /// Called for every dunning event
+    fn notify(&self, event: &DunningEvent);
+}
+
+/// Dunning tracker
+pub struct DunningEngine {
+    config: DunningConfig,
+    states: Arc<RwLock<HashMap<Uuid, DunningState>>>,
+    notifiers: Arc<RwLock<Vec<Arc<dyn DunningNotifier>>>>,
+    events: broadcast::Sender<DunningEvent>,
+}
+
+impl DunningEngine {
+    /// Create engine with a policy
+    pub fn new(config: DunningConfig) -> Self {
+        let (events, _) = broadcast::channel(1024);
+        Self {
+            config,
+            states: Arc::new(RwLock::new(HashMap::new())),
+            notifiers: Arc::new(RwLock::new(Vec::new())),
+            events,
+        }
+    }
+
+    /// Active policy
+    pub fn config(&self) -> &DunningConfig {
+        &self.config
+    }
+
+    /// Register a notification hook
+    pub fn add_notifier(&self, notifier: Arc<dyn DunningNotifier>) {
+        self.notifiers.write().push(notifier);
+    }
+
+    /// Subscribe to dunning events (SOC/CRM integration)
+    pub fn subscribe(&self) -> broadcast::Receiver<DunningEvent> {
+        self.events.subscribe()
+    }
+
+    /// Get a tenant's dunning state
+    pub fn get(&self, tenant_id: Uuid) -> Option<DunningState> {
+        self.states.read().get(&tenant_id).cloned()
+    }
+
+    /// Tenants with retries pending
+    pub fn active(&self) -> Vec<DunningState> {
+        self.states.read()
+            .values()
+            .filter(|s| s.status == DunningStatus::Active)
+            .cloned()
+            .collect()
+    }
+
+    /// Record a declined charge, scheduling the next retry or applying the
+    /// final action once retries are exhausted
+    pub fn record_failure(
+        &self,
+        tenant_id: Uuid,
+        invoice_id: Uuid,
+        amount: Decimal,
+        reason: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<DunningEvent> {
+        let mut events = Vec::new();
+        {
+            let mut states = self.states.write();
+            let state = states.entry(tenant_id)
+                .and_modify(|s| {
+                    // A new invoice failing after resolution starts a new cycle
+                    if s.status == DunningStatus::Resolved {
+                        *s = Self::new_state(&self.config, tenant_id, invoice_id, amount, now);
+                    }
+                })
+                .or_insert_with(|| Self::new_state(&self.config, tenant_id, invoice_id, amount, now));
+
+            if state.status == DunningStatus::Exhausted {
+                return events;
+            }
+
+            state.attempts += 1;
+            state.last_error = Some(reason.to_string());
+            events.push(DunningEvent::PaymentFailed {
+                tenant_id,
+                invoice_id: state.invoice_id,
+                amount: state.amount,
+                attempt: state.attempts,
+                reason: reason.to_string(),
+                at: now,
+            });
+
+            if state.attempts == 1 {
+                events.push(DunningEvent::GracePeriodStarted {
+                    tenant_id,
+                    invoice_id: state.invoice_id,
+                    grace_until: state.grace_until,
+                });
+            }
+
+            if state.attempts >= self.config.max_failures || now >= state.grace_until {
+                events.extend(self.exhaust(state, now));
+            } else {
+                let retry_at = (now + self.config.retry_delay(state.attempts)).min(state.grace_until);
+                state.next_attempt = Some(retry_at);
+
+                let email_type = if state.attempts + 1 >= self.config.max_failures {
+                    DunningEmailType::FinalWarning
+                } else if state.attempts == 1 {
+                    DunningEmailType::FirstReminder
+                } else {
+                    DunningEmailType::SecondReminder
+                };
+                state.emails_sent.push(DunningEmail { email_type, sent_at: now });
+
+                events.push(DunningEvent::NoticeSent { tenant_id, email_type });
+                events.push(DunningEvent::RetryScheduled {
+                    tenant_id,
+                    invoice_id: state.invoice_id,
+                    attempt: state.attempts + 1,
+                    retry_at,
+                });
+            }
+        }
+
+        self.publish(&events);
+        events
+    }
+
+    /// Apply the final action if the tenant's grace period ended by `now`
+    pub fn expire_grace(&self, tenant_id: Uuid, now: DateTime<Utc>) -> Vec<DunningEvent> {
+        let mut events = Vec::new();
+        {
+            let mut states = self.states.write();
+            if let Some(state) = states.get_mut(&tenant_id) {
+                if state.status == DunningStatus::Active && state.grace_until <= now {
+                    events.extend(self.exhaust(state, now));
+                }
+            }
+        }
+
+        self.publish(&events);
+        events
+    }
+
+    /// Record a successful charge; ends dunning for the tenant
+    pub fn record_success(&self, tenant_id: Uuid, now: DateTime<Utc>) -> Vec<DunningEvent> {
+        let mut events = Vec::new();
+        {
+            let mut states = self.states.write();
+            if let Some(state) = states.get_mut(&tenant_id) {
+                if state.status != DunningStatus::Resolved {
+                    state.status = DunningStatus::Resolved;
+                    state.next_attempt = None;
+                    state.emails_sent.push(DunningEmail {
+                        email_type: DunningEmailType::PaymentRecovered,
+                        sent_at: now,
+                    });
+                    events.push(DunningEvent::NoticeSent {
+                        tenant_id,
+                        email_type: DunningEmailType::PaymentRecovered,
+                    });
+                    events.push(DunningEvent::Recovered {
+                        tenant_id,
+                        invoice_id: state.invoice_id,
+                        attempts: state.attempts,
+                        at: now,
+                    });
+                }
+            }
+        }
+
+        self.publish(&events);
+        events
+    }
+
+    fn new_state(config: &DunningConfig, tenant_id: Uuid, invoice_id: Uuid, amount: Decimal, now: DateTime<Utc>) -> DunningState {
+        DunningState {
+            tenant_id,
+            invoice_id,
+            amount,
+            attempts: 0,
+            started_at: now,
+            next_attempt: None,
+            grace_until: now + config.grace_period,
+            last_error: None,
+            emails_sent: Vec::new(),
+            status: DunningStatus::Active,
+        }
+    }
+
+    fn exhaust(&self, state: &mut DunningState, now: DateTime<Utc>) -> Vec<DunningEvent> {
+        state.status = DunningStatus::Exhausted;
+        state.next_attempt = None;
+
+        let tenant_id = state.tenant_id;
+        let invoice_id = state.invoice_id;
+        let (email_type, event) = match self.config.final_action {
+            DunningAction::Suspend => (
+                DunningEmailType::AccountSuspension,
+                DunningEvent::Suspended { tenant_id, invoice_id, at: now },
+            ),
+            DunningAction::Cancel => (
+                DunningEmailType::AccountCancellation,
+                DunningEvent::Canceled { tenant_id, invoice_id, at: now },
+            ),
+        };
+        state.emails_sent.push(DunningEmail { email_type, sent_at: now });
+        tracing::warn!("Dunning exhausted for tenant {} after {} attempts", tenant_id, state.attempts);
+
+        vec![DunningEvent::NoticeSent { tenant_id, email_type }, event]
+    }
+
+    fn publish(&self, events: &[DunningEvent]) {
+        let notifiers = self.notifiers.read().clone();
+        for event in events {
+            for notifier in &notifiers {
+                notifier.notify(event);
+            }
+            // No subscribers is fine
+            let _ = self.events.send(event.clone());
+        }
+    }
+}
+
+impl Default for DunningEngine {
+    fn default() -> Self { Self::new(DunningConfig::default()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    /// Notifier collecting event types
+    #[derive(Default)]
+    struct Recorder(RwLock<Vec<String>>);
+
+    impl DunningNotifier for Recorder {
+        fn notify(&self, event: &DunningEvent) {
+            self.0.write().push(kind(event));
+        }
+    }
+
+    fn kind(event: &DunningEvent) -> String {
+        serde_json::to_value(event).unwrap()["type"].as_str().unwrap().to_string()
+    }
+
+    fn kinds(events: &[DunningEvent]) -> Vec<String> {
+        events.iter().map(kind).collect()
+    }
+
+    fn notices(state: &DunningState) -> Vec<DunningEmailType> {
+        state.emails_sent.iter().map(|e| e.email_type).collect()
+    }
+
+    #[test]
+    fn test_retry_delay_schedule() {
+        let config = DunningConfig::default();
+        assert_eq!(config.retry_delay(1), Duration::days(1));
+        assert_eq!(config.retry_delay(2), Duration::days(3));
+        assert_eq!(config.retry_delay(4), Duration::days(7));
+        // The last delay repeats past the schedule
+        assert_eq!(config.retry_delay(9), Duration::days(7));
+
+        let empty = DunningConfig { retry_schedule: vec![], ..DunningConfig::default() };
+        assert_eq!(empty.retry_delay(1), Duration::days(1));
+    }
+
+    #[test]
+    fn test_failures_escalate_to_suspension() {
+        let engine = DunningEngine::default();
+        let (tenant_id, invoice_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+
+        let events = engine.record_failure(tenant_id, invoice_id, dec!(99), "card_declined", now);
+        assert_eq!(kinds(&events), ["payment_failed", "grace_period_started", "notice_sent", "retry_scheduled"]);
+        assert!(matches!(events[3], DunningEvent::RetryScheduled { attempt: 2, retry_at, .. } if retry_at == now + Duration::days(1)));
+
+        let day = |n| now + Duration::days(n);
+        assert_eq!(kinds(&engine.record_failure(tenant_id, invoice_id, dec!(99), "card_declined", day(1))),
+            ["payment_failed", "notice_sent", "retry_scheduled"]);
+        engine.record_failure(tenant_id, invoice_id, dec!(99), "insufficient_funds", day(4));
+        let state = engine.get(tenant_id).unwrap();
+        assert_eq!(state.attempts, 3);
+        assert_eq!(state.next_attempt, Some(day(9)));
+        assert_eq!(state.last_error.as_deref(), Some("insufficient_funds"));
+        assert_eq!(notices(&state), [DunningEmailType::FirstReminder, DunningEmailType::SecondReminder, DunningEmailType::FinalWarning]);
+
+        let events = engine.record_failure(tenant_id, invoice_id, dec!(99), "card_declined", day(9));
+        assert_eq!(kinds(&events), ["payment_failed", "notice_sent", "suspended"]);
+        let state = engine.get(tenant_id).unwrap();
+        assert_eq!((state.status, state.next_attempt), (DunningStatus::Exhausted, None));
+        assert_eq!(notices(&state).last(), Some(&DunningEmailType::AccountSuspension));
+        assert!(engine.active().is_empty());
+
+        // Exhausted accounts take no further failures
+        assert!(engine.record_failure(tenant_id, invoice_id, dec!(99), "card_declined", day(10)).is_empty());
+        assert_eq!(engine.get(tenant_id).unwrap().attempts, 4);
+    }
+
+    #[test]
+    fn test_grace_period_caps_retries_and_expires() {
+        let engine = DunningEngine::new(DunningConfig {
+            retry_schedule: vec![Duration::days(5)],
+            grace_period: Duration::days(2),
+            max_failures: 10,
+            final_action: DunningAction::Cancel,
+        });
+        let (tenant_id, invoice_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+
+        engine.record_failure(tenant_id, invoice_id, dec!(10), "card_declined", now);
+        let state = engine.get(tenant_id).unwrap();
+        assert_eq!(state.next_attempt, Some(state.grace_until));
+        assert_eq!(state.grace_until, now + Duration::days(2));
+
+        assert!(engine.expire_grace(tenant_id, now + Duration::days(1)).is_empty());
+        assert_eq!(kinds(&engine.expire_grace(tenant_id, now + Duration::days(2))), ["notice_sent", "canceled"]);
+        assert_eq!(notices(&engine.get(tenant_id).unwrap()).last(), Some(&DunningEmailType::AccountCancellation));
+        assert!(engine.expire_grace(tenant_id, now + Duration::days(3)).is_empty());
+
+        // A failure past the grace period goes straight to the final action
+        let late = Uuid::new_v4();
+        engine.record_failure(late, invoice_id, dec!(10), "card_declined", now);
+        let events = engine.record_failure(late, invoice_id, dec!(10), "card_declined", now + Duration::days(3));
+        assert_eq!(kinds(&events), ["payment_failed", "notice_sent", "canceled"]);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_and_new_cycle() {
+        let engine = DunningEngine::default();
+        let recorder = Arc::new(Recorder::default());
+        engine.add_notifier(recorder.clone());
+        let mut events = engine.subscribe();
+        let (tenant_id, first, second) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+
+        // Paying without dunning is a no-op
+        assert!(engine.record_success(tenant_id, now).is_empty());
+
+        engine.record_failure(tenant_id, first, dec!(50), "card_declined", now);
+        let recovered = engine.record_success(tenant_id, now + Duration::days(1));
+        assert_eq!(kinds(&recovered), ["notice_sent", "recovered"]);
+        assert!(matches!(recovered[1], DunningEvent::Recovered { attempts: 1, invoice_id, .. } if invoice_id == first));
+        let state = engine.get(tenant_id).unwrap();
+        assert_eq!((state.status, state.next_attempt), (DunningStatus::Resolved, None));
+        assert!(engine.record_success(tenant_id, now + Duration::days(2)).is_empty());
+
+        // A later invoice failing starts over
+        engine.record_failure(tenant_id, second, dec!(75), "card_declined", now + Duration::days(30));
+        let state = engine.get(tenant_id).unwrap();
+        assert_eq!((state.invoice_id, state.amount, state.attempts), (second, dec!(75), 1));
+        assert_eq!(notices(&state), [DunningEmailType::FirstReminder]);
+
+        let published: Vec<String> = std::iter::from_fn(|| events.try_recv().ok()).map(|e| kind(&e)).collect();
+        assert_eq!(published, *recorder.0.read());
+        assert_eq!(published.len(), 10);
+    }
+}
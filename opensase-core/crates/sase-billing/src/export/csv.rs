@@ -0,0 +1,111 @@
+//! CSV encoding (RFC 4180) of usage rows
+
+use super::UsageRow;
+use chrono::SecondsFormat;
+use futures::stream::{self, Stream, StreamExt};
+
+const HEADER: &[&str] = &[
+    "tenant_id",
+    "granularity",
+    "period_start",
+    "period_end",
+    "bandwidth_ingress_gb",
+    "bandwidth_egress_gb",
+    "active_users",
+    "active_devices",
+    "security_events",
+    "api_requests",
+    "rbi_session_minutes",
+    "rbi_streamed_gb",
+    "rbi_peak_sessions",
+];
+
+/// Whole export as one buffer
+pub(crate) fn encode(rows: &[UsageRow]) -> Vec<u8> {
+    let mut out = header();
+    for row in rows {
+        write_row(row, &mut out);
+    }
+    out.into_bytes()
+}
+
+/// Header line followed by chunks of `chunk_rows` rows, for streamed
+/// downloads that never hold the whole document
+pub(crate) fn chunks(rows: Vec<UsageRow>, chunk_rows: usize) -> impl Stream<Item = String> + Send {
+    let chunk_rows = chunk_rows.max(1);
+    let body = stream::unfold(rows.into_iter(), move |mut rows| async move {
+        let mut chunk = String::new();
+        for row in rows.by_ref().take(chunk_rows) {
+            write_row(&row, &mut chunk);
+        }
+        (!chunk.is_empty()).then_some((chunk, rows))
+    });
+    stream::once(async { header() }).chain(body)
+}
+
+fn header() -> String {
+    let mut line = HEADER.join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn write_row(row: &UsageRow, out: &mut String) {
+    let fields = [
+        row.tenant_id.to_string(),
+        row.granularity.as_str().to_string(),
+        row.period_start.to_rfc3339_opts(SecondsFormat::Secs, true),
+        row.period_end.to_rfc3339_opts(SecondsFormat::Secs, true),
+        row.bandwidth_ingress_gb.to_string(),
+        row.bandwidth_egress_gb.to_string(),
+        row.active_users.to_string(),
+        row.active_devices.to_string(),
+        row.security_events.to_string(),
+        row.api_requests.to_string(),
+        row.rbi_session_minutes.to_string(),
+        row.rbi_streamed_gb.to_string(),
+        row.rbi_peak_sessions.to_string(),
+    ];
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_field(field, out);
+    }
+    out.push_str("\r\n");
+}
+
+/// Quote fields containing separators, quotes or line breaks
+fn push_field(field: &str, out: &mut String) {
+    if field.contains([',', '"', '\r', '\n']) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(value: &str) -> String {
+        let mut out = String::new();
+        push_field(value, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_plain_fields_unquoted() {
+        assert_eq!(field("daily"), "daily");
+        assert_eq!(field("2.5"), "2.5");
+    }
+
+    #[test]
+    fn test_special_fields_quoted() {
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(field("cr\rhere"), "\"cr\rhere\"");
+    }
+}
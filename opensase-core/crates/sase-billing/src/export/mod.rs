@@ -0,0 +1,876 @@
+//! Usage Export
+//!
+//! Raw metering data for finance teams: per-tenant hourly or daily usage
+//! over a date range, as CSV or Parquet.
+//!
+//! ```text
+//! GET  /tenants/:tenant_id/usage/export   ──► streamed download
+//! POST /tenants/:tenant_id/usage/exports  ──► background job ──► inline
+//!                                                            └─► object store (large)
+//! GET  /usage/exports/:id/download        ──► bytes, or 303 to a presigned URL
+//! ```
+//!
+//! Direct downloads stream CSV in row chunks; Parquet needs its footer
+//! written last, so it is encoded whole before the response starts. Jobs
+//! encode off the async runtime and upload outputs above
+//! [`ExportConfig::inline_limit_bytes`] to an S3-compatible bucket.
+
+mod csv;
+mod parquet;
+pub mod s3;
+
+pub use s3::{ObjectStore, S3Config, S3Store};
+
+use crate::metering::MeteringEngine;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use futures::StreamExt;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use uuid::Uuid;
+
+// =============================================================================
+// Configuration and requests
+// =============================================================================
+
+/// Export configuration
+#[derive(Debug, Clone)]
+pub struct ExportConfig {
+    /// Job outputs larger than this go to the object store, when one is set
+    pub inline_limit_bytes: usize,
+    /// Rows per chunk of a streamed CSV download
+    pub stream_chunk_rows: usize,
+    /// Rows per Parquet row group
+    pub row_group_rows: usize,
+    /// Object key prefix for uploaded exports
+    pub key_prefix: String,
+    /// Lifetime of presigned download links
+    pub download_url_ttl: std::time::Duration,
+    /// Longest date range a single export may cover, in days
+    pub max_range_days: i64,
+    /// How long finished jobs stay listed
+    pub job_retention: Duration,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            inline_limit_bytes: 8 * 1024 * 1024,
+            stream_chunk_rows: 500,
+            row_group_rows: 8192,
+            key_prefix: "usage-exports".to_string(),
+            download_url_ttl: std::time::Duration::from_secs(15 * 60),
+            max_range_days: 366,
+            job_retention: Duration::hours(24),
+        }
+    }
+}
+
+/// Output file format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// RFC 4180 comma-separated values
+    #[default]
+    Csv,
+    /// Apache Parquet, Snappy-compressed
+    Parquet,
+}
+
+impl ExportFormat {
+    /// MIME type of the output
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    /// File extension of the output
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+/// Aggregation period of each row
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// One row per hour with usage
+    Hourly,
+    /// One row per day with usage
+    #[default]
+    Daily,
+}
+
+impl Granularity {
+    /// Name as written into exports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+        }
+    }
+}
+
+/// What to export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    /// Tenant whose usage is exported
+    pub tenant_id: Uuid,
+    /// Row period
+    pub granularity: Granularity,
+    /// Output format
+    pub format: ExportFormat,
+    /// First day, inclusive (UTC)
+    pub from: NaiveDate,
+    /// Last day, inclusive (UTC)
+    pub to: NaiveDate,
+}
+
+impl ExportRequest {
+    /// Download file name
+    pub fn filename(&self) -> String {
+        format!(
+            "usage-{}-{}-{}-{}.{}",
+            self.tenant_id,
+            self.granularity.as_str(),
+            self.from,
+            self.to,
+            self.format.extension()
+        )
+    }
+}
+
+/// One exported period of usage
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageRow {
+    /// Tenant
+    pub tenant_id: Uuid,
+    /// Row period
+    pub granularity: Granularity,
+    /// Period start (inclusive)
+    pub period_start: DateTime<Utc>,
+    /// Period end (exclusive)
+    pub period_end: DateTime<Utc>,
+    /// Ingress bandwidth
+    pub bandwidth_ingress_gb: f64,
+    /// Egress bandwidth
+    pub bandwidth_egress_gb: f64,
+    /// Peak active users in the period
+    pub active_users: u64,
+    /// Peak active devices in the period
+    pub active_devices: u64,
+    /// Security events processed
+    pub security_events: u64,
+    /// API requests
+    pub api_requests: u64,
+    /// Browser isolation session-minutes
+    pub rbi_session_minutes: f64,
+    /// Browser isolation stream volume
+    pub rbi_streamed_gb: f64,
+    /// Peak concurrent browser isolation sessions
+    pub rbi_peak_sessions: u64,
+}
+
+// =============================================================================
+// Jobs
+// =============================================================================
+
+/// Export job state
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ExportStatus {
+    /// Accepted, not started
+    Queued,
+    /// Reading usage and encoding
+    Running,
+    /// Output ready for download
+    Completed,
+    /// Generation or upload failed
+    Failed {
+        /// Failure description
+        reason: String,
+    },
+}
+
+/// Background export job
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    /// Job id
+    pub id: Uuid,
+    /// What is being exported
+    pub request: ExportRequest,
+    /// Current state
+    pub status: ExportStatus,
+    /// Rows written, once completed
+    pub rows: Option<usize>,
+    /// Output size, once completed
+    pub size_bytes: Option<usize>,
+    /// Object store key, when the output was uploaded
+    pub object_key: Option<String>,
+    /// Submission time
+    pub created_at: DateTime<Utc>,
+    /// Completion or failure time
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Output held in memory
+    #[serde(skip)]
+    data: Option<Arc<Vec<u8>>>,
+}
+
+/// Where a finished export can be fetched
+#[derive(Debug, Clone)]
+pub enum ExportDownload {
+    /// Output held by the exporter
+    Inline {
+        /// Download file name
+        filename: String,
+        /// MIME type
+        content_type: &'static str,
+        /// File contents
+        data: Arc<Vec<u8>>,
+    },
+    /// Presigned object store URL
+    Redirect(String),
+}
+
+// =============================================================================
+// Exporter
+// =============================================================================
+
+/// Per-tenant usage exporter
+pub struct UsageExporter {
+    metering: Arc<MeteringEngine>,
+    store: Option<Arc<dyn ObjectStore>>,
+    config: ExportConfig,
+    jobs: RwLock<HashMap<Uuid, ExportJob>>,
+}
+
+impl UsageExporter {
+    /// Create an exporter over the metering engine's aggregates
+    pub fn new(metering: Arc<MeteringEngine>) -> Self {
+        Self {
+            metering,
+            store: None,
+            config: ExportConfig::default(),
+            jobs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Upload large job outputs to an object store
+    pub fn with_store(mut self, store: Arc<dyn ObjectStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Override the default configuration
+    pub fn with_config(mut self, config: ExportConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Rows for a request, oldest first
+    pub fn rows(&self, request: &ExportRequest) -> Result<Vec<UsageRow>, ExportError> {
+        self.validate(request)?;
+
+        let rows = match request.granularity {
+            Granularity::Hourly => self.metering
+                .hourly_usage(request.tenant_id, request.from, request.to)
+                .into_iter()
+                .map(|(start, usage)| UsageRow {
+                    tenant_id: usage.tenant_id,
+                    granularity: Granularity::Hourly,
+                    period_start: start,
+                    period_end: start + Duration::hours(1),
+                    bandwidth_ingress_gb: usage.bandwidth_ingress_gb,
+                    bandwidth_egress_gb: usage.bandwidth_egress_gb,
+                    active_users: usage.active_users,
+                    active_devices: usage.active_devices,
+                    security_events: usage.security_events,
+                    api_requests: usage.api_requests,
+                    rbi_session_minutes: usage.rbi_session_minutes,
+                    rbi_streamed_gb: usage.rbi_streamed_gb,
+                    rbi_peak_sessions: usage.rbi_peak_sessions,
+                })
+                .collect(),
+            Granularity::Daily => self.metering
+                .daily_usage(request.tenant_id, request.from, request.to)
+                .into_iter()
+                .map(|usage| {
+                    let start = usage.date.and_time(NaiveTime::MIN).and_utc();
+                    UsageRow {
+                        tenant_id: usage.tenant_id,
+                        granularity: Granularity::Daily,
+                        period_start: start,
+                        period_end: start + Duration::days(1),
+                        bandwidth_ingress_gb: usage.bandwidth_ingress_gb,
+                        bandwidth_egress_gb: usage.bandwidth_egress_gb,
+                        active_users: usage.peak_users,
+                        active_devices: usage.peak_devices,
+                        security_events: usage.security_events,
+                        api_requests: usage.api_requests,
+                        rbi_session_minutes: usage.rbi_session_minutes,
+                        rbi_streamed_gb: usage.rbi_streamed_gb,
+                        rbi_peak_sessions: usage.rbi_peak_sessions,
+                    }
+                })
+                .collect(),
+        };
+
+        Ok(rows)
+    }
+
+    /// Encode rows on the blocking pool
+    pub async fn encode(&self, format: ExportFormat, rows: Vec<UsageRow>) -> Result<Vec<u8>, ExportError> {
+        let row_group_rows = self.config.row_group_rows;
+        tokio::task::spawn_blocking(move || match format {
+            ExportFormat::Csv => Ok(csv::encode(&rows)),
+            ExportFormat::Parquet => parquet::encode(&rows, row_group_rows),
+        })
+        .await
+        .map_err(|e| ExportError::Encoding(format!("encoder task failed: {}", e)))?
+    }
+
+    /// Queue a background export. Returns immediately; poll
+    /// [`UsageExporter::job`] for completion.
+    pub fn submit(self: &Arc<Self>, request: ExportRequest) -> Result<ExportJob, ExportError> {
+        self.validate(&request)?;
+        self.purge_finished();
+
+        let job = ExportJob {
+            id: Uuid::new_v4(),
+            request: request.clone(),
+            status: ExportStatus::Queued,
+            rows: None,
+            size_bytes: None,
+            object_key: None,
+            created_at: Utc::now(),
+            finished_at: None,
+            data: None,
+        };
+        self.jobs.write().insert(job.id, job.clone());
+
+        let exporter = self.clone();
+        let id = job.id;
+        tokio::spawn(async move { exporter.run(id, request).await });
+
+        Ok(job)
+    }
+
+    /// Job by id
+    pub fn job(&self, id: Uuid) -> Option<ExportJob> {
+        self.jobs.read().get(&id).cloned()
+    }
+
+    /// Jobs for a tenant, newest first
+    pub fn jobs_for(&self, tenant_id: Uuid) -> Vec<ExportJob> {
+        let mut jobs: Vec<_> = self.jobs.read().values()
+            .filter(|job| job.request.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+
+    /// Output of a completed job
+    pub fn download(&self, id: Uuid) -> Result<ExportDownload, ExportError> {
+        let job = self.job(id).ok_or(ExportError::NotFound(id))?;
+        match &job.status {
+            ExportStatus::Completed => {}
+            ExportStatus::Failed { reason } => return Err(ExportError::Failed(reason.clone())),
+            _ => return Err(ExportError::NotReady),
+        }
+
+        match (job.data, job.object_key, &self.store) {
+            (Some(data), _, _) => Ok(ExportDownload::Inline {
+                filename: job.request.filename(),
+                content_type: job.request.format.content_type(),
+                data,
+            }),
+            (None, Some(key), Some(store)) => {
+                store.download_url(&key, self.config.download_url_ttl).map(ExportDownload::Redirect)
+            }
+            _ => Err(ExportError::Storage("export output is no longer available".to_string())),
+        }
+    }
+
+    fn validate(&self, request: &ExportRequest) -> Result<(), ExportError> {
+        if request.to < request.from {
+            return Err(ExportError::InvalidRequest("`to` is before `from`".to_string()));
+        }
+        let days = (request.to - request.from).num_days() + 1;
+        if days > self.config.max_range_days {
+            return Err(ExportError::InvalidRequest(format!(
+                "range of {} days exceeds the {} day limit",
+                days, self.config.max_range_days
+            )));
+        }
+        Ok(())
+    }
+
+    async fn run(&self, id: Uuid, request: ExportRequest) {
+        self.update(id, |job| job.status = ExportStatus::Running);
+
+        match self.generate(id, &request).await {
+            Ok((rows, size, output)) => {
+                tracing::info!(%id, tenant_id = %request.tenant_id, rows, size, "Usage export completed");
+                self.update(id, |job| {
+                    job.status = ExportStatus::Completed;
+                    job.rows = Some(rows);
+                    job.size_bytes = Some(size);
+                    match output {
+                        Output::Inline(data) => job.data = Some(Arc::new(data)),
+                        Output::Stored(key) => job.object_key = Some(key),
+                    }
+                    job.finished_at = Some(Utc::now());
+                });
+            }
+            Err(e) => {
+                tracing::warn!(%id, tenant_id = %request.tenant_id, error = %e, "Usage export failed");
+                self.update(id, |job| {
+                    job.status = ExportStatus::Failed { reason: e.to_string() };
+                    job.finished_at = Some(Utc::now());
+                });
+            }
+        }
+    }
+
+    async fn generate(&self, id: Uuid, request: &ExportRequest) -> Result<(usize, usize, Output), ExportError> {
+        let rows = self.rows(request)?;
+        let count = rows.len();
+        let data = self.encode(request.format, rows).await?;
+        let size = data.len();
+
+        match &self.store {
+            Some(store) if size > self.config.inline_limit_bytes => {
+                let key = format!(
+                    "{}/{}/{}-{}",
+                    self.config.key_prefix.trim_end_matches('/'),
+                    request.tenant_id,
+                    id,
+                    request.filename()
+                );
+                store.put(&key, request.format.content_type(), data).await?;
+                Ok((count, size, Output::Stored(key)))
+            }
+            _ => Ok((count, size, Output::Inline(data))),
+        }
+    }
+
+    fn update(&self, id: Uuid, apply: impl FnOnce(&mut ExportJob)) {
+        if let Some(job) = self.jobs.write().get_mut(&id) {
+            apply(job);
+        }
+    }
+
+    /// Drop finished jobs past retention, releasing inline outputs
+    fn purge_finished(&self) {
+        let cutoff = Utc::now() - self.config.job_retention;
+        self.jobs.write().retain(|_, job| job.finished_at.is_none_or(|at| at > cutoff));
+    }
+
+    /// Create Axum router
+    pub fn router(exporter: Arc<Self>) -> Router {
+        Router::new()
+            .route("/tenants/:tenant_id/usage/export", get(stream_handler))
+            .route("/tenants/:tenant_id/usage/exports", post(submit_handler).get(list_handler))
+            .route("/usage/exports/:id", get(job_handler))
+            .route("/usage/exports/:id/download", get(download_handler))
+            .with_state(exporter)
+    }
+}
+
+/// Generated job output
+enum Output {
+    Inline(Vec<u8>),
+    Stored(String),
+}
+
+// =============================================================================
+// HTTP handlers
+// =============================================================================
+
+/// Export parameters, as query string or JSON body
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(default)]
+    granularity: Granularity,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+impl ExportParams {
+    fn into_request(self, tenant_id: Uuid) -> ExportRequest {
+        ExportRequest {
+            tenant_id,
+            granularity: self.granularity,
+            format: self.format,
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+fn error_response(e: ExportError) -> Response {
+    let status = match e {
+        ExportError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        ExportError::NotFound(_) => StatusCode::NOT_FOUND,
+        ExportError::NotReady | ExportError::Failed(_) => StatusCode::CONFLICT,
+        ExportError::Storage(_) => StatusCode::BAD_GATEWAY,
+        ExportError::Encoding(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string()).into_response()
+}
+
+fn attachment(filename: &str, content_type: &'static str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Direct download handler
+async fn stream_handler(
+    State(exporter): State<Arc<UsageExporter>>,
+    Path(tenant_id): Path<Uuid>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let request = params.into_request(tenant_id);
+    let rows = match exporter.rows(&request) {
+        Ok(rows) => rows,
+        Err(e) => return error_response(e),
+    };
+
+    let body = match request.format {
+        ExportFormat::Csv => Body::from_stream(
+            csv::chunks(rows, exporter.config.stream_chunk_rows).map(Ok::<_, Infallible>),
+        ),
+        ExportFormat::Parquet => match exporter.encode(ExportFormat::Parquet, rows).await {
+            Ok(data) => Body::from(data),
+            Err(e) => return error_response(e),
+        },
+    };
+    attachment(&request.filename(), request.format.content_type(), body)
+}
+
+/// Background export submission handler
+async fn submit_handler(
+    State(exporter): State<Arc<UsageExporter>>,
+    Path(tenant_id): Path<Uuid>,
+    Json(params): Json<ExportParams>,
+) -> Response {
+    match exporter.submit(params.into_request(tenant_id)) {
+        Ok(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Tenant job listing handler
+async fn list_handler(
+    State(exporter): State<Arc<UsageExporter>>,
+    Path(tenant_id): Path<Uuid>,
+) -> Json<Vec<ExportJob>> {
+    Json(exporter.jobs_for(tenant_id))
+}
+
+/// Job status handler
+async fn job_handler(
+    State(exporter): State<Arc<UsageExporter>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match exporter.job(id) {
+        Some(job) => Json(job).into_response(),
+        None => error_response(ExportError::NotFound(id)),
+    }
+}
+
+/// Job output download handler
+async fn download_handler(
+    State(exporter): State<Arc<UsageExporter>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match exporter.download(id) {
+        Ok(ExportDownload::Inline { filename, content_type, data }) => {
+            attachment(&filename, content_type, Body::from(data.as_ref().clone()))
+        }
+        Ok(ExportDownload::Redirect(url)) => Redirect::to(&url).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+/// Export error
+#[derive(Debug, Clone)]
+pub enum ExportError {
+    /// Request rejected before generation
+    InvalidRequest(String),
+    /// Unknown export job
+    NotFound(Uuid),
+    /// Job has not finished
+    NotReady,
+    /// Job finished without output
+    Failed(String),
+    /// CSV or Parquet encoding failed
+    Encoding(String),
+    /// Object store unreachable or rejected the request
+    Storage(String),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRequest(e) => write!(f, "Invalid export request: {}", e),
+            Self::NotFound(id) => write!(f, "Export job {} not found", id),
+            Self::NotReady => write!(f, "Export is still being generated"),
+            Self::Failed(e) => write!(f, "Export failed: {}", e),
+            Self::Encoding(e) => write!(f, "Export encoding error: {}", e),
+            Self::Storage(e) => write!(f, "Export storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metering::{UsageEvent, UsageMetric};
+    use chrono::TimeZone;
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn event(tenant_id: Uuid, timestamp: DateTime<Utc>, metric: UsageMetric, value: f64) -> UsageEvent {
+        UsageEvent {
+            tenant_id,
+            timestamp,
+            metric,
+            value,
+            dimensions: HashMap::new(),
+            idempotency_key: None,
+        }
+    }
+
+    fn request(tenant_id: Uuid, granularity: Granularity, format: ExportFormat) -> ExportRequest {
+        ExportRequest {
+            tenant_id,
+            granularity,
+            format,
+            from: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            to: NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+        }
+    }
+
+    /// Exporter over two hours of usage on 2024-03-05
+    fn exporter(tenant_id: Uuid) -> UsageExporter {
+        let metering = Arc::new(MeteringEngine::new());
+        let nine = Utc.with_ymd_and_hms(2024, 3, 5, 9, 15, 0).unwrap();
+        let ten = Utc.with_ymd_and_hms(2024, 3, 5, 10, 30, 0).unwrap();
+        metering.record(event(tenant_id, nine, UsageMetric::BandwidthIngressGB, 1.5));
+        metering.record(event(tenant_id, nine, UsageMetric::APIRequests, 40.0));
+        metering.record(event(tenant_id, ten, UsageMetric::BandwidthIngressGB, 2.5));
+        metering.record(event(tenant_id, ten, UsageMetric::APIRequests, 2.0));
+        UsageExporter::new(metering)
+    }
+
+    fn job(tenant_id: Uuid, created_at: DateTime<Utc>, finished_at: Option<DateTime<Utc>>) -> ExportJob {
+        ExportJob {
+            id: Uuid::new_v4(),
+            request: request(tenant_id, Granularity::Daily, ExportFormat::Csv),
+            status: ExportStatus::Queued,
+            rows: None,
+            size_bytes: None,
+            object_key: None,
+            created_at,
+            finished_at,
+            data: None,
+        }
+    }
+
+    #[test]
+    fn test_hourly_and_daily_rows() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+
+        let hourly = exporter.rows(&request(tenant_id, Granularity::Hourly, ExportFormat::Csv)).unwrap();
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(hourly[0].period_start, Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap());
+        assert_eq!(hourly[0].period_end, Utc.with_ymd_and_hms(2024, 3, 5, 10, 0, 0).unwrap());
+        assert_eq!(hourly[0].api_requests, 40);
+        assert_eq!(hourly[1].bandwidth_ingress_gb, 2.5);
+
+        let daily = exporter.rows(&request(tenant_id, Granularity::Daily, ExportFormat::Csv)).unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].period_start, Utc.with_ymd_and_hms(2024, 3, 5, 0, 0, 0).unwrap());
+        assert_eq!(daily[0].period_end, Utc.with_ymd_and_hms(2024, 3, 6, 0, 0, 0).unwrap());
+        assert_eq!(daily[0].bandwidth_ingress_gb, 4.0);
+        assert_eq!(daily[0].api_requests, 42);
+    }
+
+    #[test]
+    fn test_invalid_ranges_rejected() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+
+        let mut reversed = request(tenant_id, Granularity::Daily, ExportFormat::Csv);
+        std::mem::swap(&mut reversed.from, &mut reversed.to);
+        assert!(matches!(exporter.rows(&reversed), Err(ExportError::InvalidRequest(_))));
+
+        let mut too_long = request(tenant_id, Granularity::Daily, ExportFormat::Csv);
+        too_long.to = too_long.from + Duration::days(366);
+        assert!(matches!(exporter.rows(&too_long), Err(ExportError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_filename() {
+        let tenant_id = Uuid::nil();
+        let request = request(tenant_id, Granularity::Hourly, ExportFormat::Parquet);
+        assert_eq!(
+            request.filename(),
+            format!("usage-{}-hourly-2024-03-01-2024-03-31.parquet", tenant_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_encoding() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+        let rows = exporter.rows(&request(tenant_id, Granularity::Daily, ExportFormat::Csv)).unwrap();
+
+        let data = exporter.encode(ExportFormat::Csv, rows).await.unwrap();
+        let text = String::from_utf8(data).unwrap();
+        let lines: Vec<_> = text.split_terminator("\r\n").collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("tenant_id,granularity,period_start,period_end,"));
+        assert_eq!(lines[0].split(',').count(), 13);
+        assert_eq!(
+            lines[1],
+            format!("{},daily,2024-03-05T00:00:00Z,2024-03-06T00:00:00Z,4,0,0,0,0,42,0,0,0", tenant_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_chunks_match_whole_encoding() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+        let rows = exporter.rows(&request(tenant_id, Granularity::Hourly, ExportFormat::Csv)).unwrap();
+
+        let whole = csv::encode(&rows);
+        let chunks: Vec<String> = csv::chunks(rows, 1).collect().await;
+
+        // Header, then one chunk per row
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat().into_bytes(), whole);
+    }
+
+    #[tokio::test]
+    async fn test_parquet_encoding() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id).with_config(ExportConfig {
+            row_group_rows: 1,
+            ..ExportConfig::default()
+        });
+        let rows = exporter.rows(&request(tenant_id, Granularity::Hourly, ExportFormat::Parquet)).unwrap();
+
+        let data = exporter.encode(ExportFormat::Parquet, rows).await.unwrap();
+        assert_eq!(&data[..4], b"PAR1");
+        assert_eq!(&data[data.len() - 4..], b"PAR1");
+
+        let path = std::env::temp_dir().join(format!("{}.parquet", Uuid::new_v4()));
+        std::fs::write(&path, &data).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        assert_eq!(metadata.num_row_groups(), 2);
+        let columns: Vec<_> = metadata.file_metadata().schema_descr().columns().iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        assert_eq!(columns.len(), 13);
+        assert_eq!(columns[0], "tenant_id");
+        assert_eq!(columns[12], "rbi_peak_sessions");
+    }
+
+    #[test]
+    fn test_jobs_listed_newest_first() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+        let now = Utc::now();
+        let older = job(tenant_id, now - Duration::minutes(5), None);
+        let newer = job(tenant_id, now, None);
+        let other = job(Uuid::new_v4(), now, None);
+        for job in [older.clone(), newer.clone(), other] {
+            exporter.jobs.write().insert(job.id, job);
+        }
+
+        let ids: Vec<_> = exporter.jobs_for(tenant_id).into_iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec![newer.id, older.id]);
+    }
+
+    #[test]
+    fn test_purge_keeps_unfinished_and_recent_jobs() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+        let now = Utc::now();
+        let running = job(tenant_id, now - Duration::days(3), None);
+        let recent = job(tenant_id, now - Duration::hours(2), Some(now - Duration::hours(1)));
+        let expired = job(tenant_id, now - Duration::days(2), Some(now - Duration::hours(25)));
+        for job in [running.clone(), recent.clone(), expired.clone()] {
+            exporter.jobs.write().insert(job.id, job);
+        }
+
+        exporter.purge_finished();
+
+        assert!(exporter.job(running.id).is_some());
+        assert!(exporter.job(recent.id).is_some());
+        assert!(exporter.job(expired.id).is_none());
+    }
+
+    #[test]
+    fn test_download_requires_completion() {
+        let tenant_id = Uuid::new_v4();
+        let exporter = exporter(tenant_id);
+        let queued = job(tenant_id, Utc::now(), None);
+        let mut failed = job(tenant_id, Utc::now(), Some(Utc::now()));
+        failed.status = ExportStatus::Failed { reason: "boom".to_string() };
+        let mut completed = job(tenant_id, Utc::now(), Some(Utc::now()));
+        completed.status = ExportStatus::Completed;
+        completed.data = Some(Arc::new(b"data".to_vec()));
+        for job in [queued.clone(), failed.clone(), completed.clone()] {
+            exporter.jobs.write().insert(job.id, job);
+        }
+
+        assert!(matches!(exporter.download(Uuid::new_v4()), Err(ExportError::NotFound(_))));
+        assert!(matches!(exporter.download(queued.id), Err(ExportError::NotReady)));
+        assert!(matches!(exporter.download(failed.id), Err(ExportError::Failed(reason)) if reason == "boom"));
+        match exporter.download(completed.id).unwrap() {
+            ExportDownload::Inline { content_type, data, .. } => {
+                assert_eq!(content_type, "text/csv; charset=utf-8");
+                assert_eq!(data.as_slice(), b"data");
+            }
+            ExportDownload::Redirect(url) => panic!("unexpected redirect to {}", url),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! S3-compatible object storage for large exports
+//!
+//! Requests are signed with AWS Signature Version 4 and use path-style
+//! addressing (`{endpoint}/{bucket}/{key}`), which AWS, MinIO, Ceph RGW and
+//! R2 all accept. Objects above the multipart threshold are uploaded in
+//! parts; downloads are handed out as presigned GET URLs so the bytes never
+//! pass back through the billing service.
+
+use super::ExportError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// S3 minimum size for every part but the last
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// S3 maximum number of parts per upload
+const MAX_PARTS: usize = 10_000;
+/// SigV4 presigned URLs are valid for at most a week
+const MAX_PRESIGN_SECS: u64 = 7 * 24 * 3600;
+
+/// Destination for exports too large to hold in memory
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Store an object, replacing any existing one under the key
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<(), ExportError>;
+
+    /// Time-limited URL a client can download the object from
+    fn download_url(&self, key: &str, expires: Duration) -> Result<String, ExportError>;
+}
+
+/// S3-compatible bucket settings
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    /// Service endpoint, e.g. `https://s3.eu-west-1.amazonaws.com`
+    pub endpoint: String,
+    /// Signing region (`us-east-1` for most non-AWS stores)
+    pub region: String,
+    /// Bucket name
+    pub bucket: String,
+    /// Access key id
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+    /// Objects larger than this are uploaded in parts
+    pub multipart_threshold_bytes: usize,
+    /// Part size for multipart uploads
+    pub part_size_bytes: usize,
+}
+
+impl S3Config {
+    /// Bucket settings with default multipart sizing
+    pub fn new(
+        endpoint: impl Into<String>,
+        region: impl Into<String>,
+        bucket: impl Into<String>,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            region: region.into(),
+            bucket: bucket.into(),
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            multipart_threshold_bytes: 64 * 1024 * 1024,
+            part_size_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Object store backed by an S3-compatible bucket
+pub struct S3Store {
+    config: S3Config,
+    scheme: String,
+    /// `host[:port]` as sent in the Host header
+    host: String,
+    /// Endpoint path prefix, without trailing slash
+    base_path: String,
+    http: reqwest::Client,
+}
+
+impl S3Store {
+    /// Create a store for the configured bucket
+    pub fn new(config: S3Config) -> Result<Self, ExportError> {
+        let endpoint = reqwest::Url::parse(&config.endpoint)
+            .map_err(|e| ExportError::Storage(format!("invalid endpoint {}: {}", config.endpoint, e)))?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(ExportError::Storage(format!("endpoint {} has no host", config.endpoint))),
+        };
+
+        Ok(Self {
+            scheme: endpoint.scheme().to_string(),
+            host,
+            base_path: endpoint.path().trim_end_matches('/').to_string(),
+            config,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Canonical URI of an object
+    fn path(&self, key: &str) -> String {
+        format!("{}/{}/{}", self.base_path, encode(&self.config.bucket, false), encode(key, true))
+    }
+
+    fn url(&self, path: &str, query: &str) -> String {
+        if query.is_empty() {
+            format!("{}://{}{}", self.scheme, self.host, path)
+        } else {
+            format!("{}://{}{}?{}", self.scheme, self.host, path, query)
+        }
+    }
+
+    /// Send a signed request; non-2xx responses become errors
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response, ExportError> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let path = self.path(key);
+        let query = canonical_query(query);
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(), path, query, self.host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let (scope, signature) = self.sign(now, &canonical_request);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key_id, scope, signed_headers, signature
+        );
+
+        let mut request = self.http.request(method.clone(), self.url(&path, &query))
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization)
+            .body(body);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+
+        let response = request.send().await
+            .map_err(|e| ExportError::Storage(format!("{} {}: {}", method, key, e)))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let message = xml_value(&text, "Message").unwrap_or(text.trim());
+            return Err(ExportError::Storage(format!("{} {} returned {}: {}", method, key, status, message)));
+        }
+        Ok(response)
+    }
+
+    /// Credential scope and hex signature for a canonical request
+    fn sign(&self, at: DateTime<Utc>, canonical_request: &str) -> (String, String) {
+        let date = at.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            at.format("%Y%m%dT%H%M%SZ"),
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut key = hmac(format!("AWS4{}", self.config.secret_access_key).as_bytes(), date.as_bytes());
+        for part in [self.config.region.as_str(), "s3", "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        (scope, hex::encode(hmac(&key, string_to_sign.as_bytes())))
+    }
+
+    async fn put_multipart(&self, key: &str, content_type: &str, data: &[u8]) -> Result<(), ExportError> {
+        let response = self.send(Method::POST, key, &[("uploads", "")], Vec::new(), Some(content_type)).await?;
+        let body = response.text().await.map_err(|e| ExportError::Storage(e.to_string()))?;
+        let upload_id = xml_value(&body, "UploadId")
+            .ok_or_else(|| ExportError::Storage("CreateMultipartUpload returned no UploadId".to_string()))?
+            .to_string();
+
+        let result = self.upload_parts(key, &upload_id, data).await;
+        if result.is_err() {
+            // Otherwise the stored parts are billed until a lifecycle rule removes them
+            let abort = self.send(Method::DELETE, key, &[("uploadId", upload_id.as_str())], Vec::new(), None).await;
+            if let Err(e) = abort {
+                tracing::warn!(key, error = %e, "Failed to abort multipart upload");
+            }
+        }
+        result
+    }
+
+    async fn upload_parts(&self, key: &str, upload_id: &str, data: &[u8]) -> Result<(), ExportError> {
+        let part_size = self.config.part_size_bytes
+            .max(MIN_PART_SIZE)
+            .max(data.len().div_ceil(MAX_PARTS));
+
+        let mut complete = String::from("<CompleteMultipartUpload>");
+        for (index, part) in data.chunks(part_size).enumerate() {
+            let number = (index + 1).to_string();
+            let query = [("partNumber", number.as_str()), ("uploadId", upload_id)];
+            let response = self.send(Method::PUT, key, &query, part.to_vec(), None).await?;
+            let etag = response.headers().get("etag")
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| ExportError::Storage(format!("part {} returned no ETag", number)))?;
+            complete.push_str(&format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", number, etag));
+        }
+        complete.push_str("</CompleteMultipartUpload>");
+
+        let response = self.send(
+            Method::POST,
+            key,
+            &[("uploadId", upload_id)],
+            complete.into_bytes(),
+            Some("application/xml"),
+        ).await?;
+
+        // Completion can fail after the 200 status line has been sent
+        let body = response.text().await.map_err(|e| ExportError::Storage(e.to_string()))?;
+        if body.contains("<Error>") {
+            let message = xml_value(&body, "Message").unwrap_or("unknown error");
+            return Err(ExportError::Storage(format!("CompleteMultipartUpload failed: {}", message)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3Store {
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<(), ExportError> {
+        if data.len() > self.config.multipart_threshold_bytes {
+            self.put_multipart(key, content_type, &data).await
+        } else {
+            self.send(Method::PUT, key, &[], data, Some(content_type)).await.map(|_| ())
+        }
+    }
+
+    fn download_url(&self, key: &str, expires: Duration) -> Result<String, ExportError> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let credential = format!(
+            "{}/{}/{}/s3/aws4_request",
+            self.config.access_key_id, now.format("%Y%m%d"), self.config.region
+        );
+        let expires = expires.as_secs().clamp(1, MAX_PRESIGN_SECS).to_string();
+        let path = self.path(key);
+        let query = canonical_query(&[
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256"),
+            ("X-Amz-Credential", &credential),
+            ("X-Amz-Date", &amz_date),
+            ("X-Amz-Expires", &expires),
+            ("X-Amz-SignedHeaders", "host"),
+        ]);
+
+        let canonical_request = format!("GET\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD", path, query, self.host);
+        let (_, signature) = self.sign(now, &canonical_request);
+        Ok(self.url(&path, &format!("{}&X-Amz-Signature={}", query, signature)))
+    }
+}
+
+/// Query string in SigV4 canonical form: encoded and sorted by name
+fn canonical_query(params: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = params.iter()
+        .map(|(name, value)| (encode(name, false), encode(value, false)))
+        .collect();
+    pairs.sort();
+    pairs.iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// SigV4 URI encoding: everything but unreserved characters, optionally
+/// leaving `/` intact for object keys
+fn encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Text of the first `<tag>` element in an S3 XML response
+fn xml_value<'a>(body: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = body.find(&open)? + open.len();
+    let end = start + body[start..].find(&format!("</{}>", tag))?;
+    Some(&body[start..end])
+}
@@ -0,0 +1,913 @@
+//! Streaming Usage Ingestion
+//!
+//! Consumes usage events published by PoPs from a message stream and feeds
+//! them to the [`MeteringEngine`] in batches. Messages are acknowledged only
+//! after their events are recorded (at-least-once), and redeliveries are
+//! dropped by event ID. A bounded queue between the consumer and the
+//! recorder provides backpressure: when recording falls behind, polling
+//! stops and the backlog stays on the broker.
+
+use crate::metering::{MeteringEngine, UsageEvent};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Message pulled from a usage stream
+#[derive(Debug, Clone)]
+pub struct SourceMessage {
+    /// Stable message ID (`stream:sequence`, `topic/partition/offset`, ...).
+    /// Events without an idempotency key are identified by it.
+    pub id: String,
+    /// JSON `UsageEvent` or array of them
+    pub payload: Vec<u8>,
+    /// Handle passed back to [`UsageSource::ack`]
+    pub ack: AckToken,
+}
+
+/// Source-specific acknowledgement handle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AckToken {
+    /// Per-message ack subject (NATS JetStream)
+    Subject(String),
+    /// Partition offset; committing an offset commits everything before it
+    Offset {
+        /// Partition
+        partition: i32,
+        /// Offset of the message
+        offset: i64,
+    },
+    /// Nothing to acknowledge
+    None,
+}
+
+/// Stream of usage events (Kafka consumer group, JetStream pull consumer, ...)
+#[async_trait]
+pub trait UsageSource: Send + Sync {
+    /// Pull up to `max` messages, waiting at most `wait`
+    async fn poll(&self, max: usize, wait: Duration) -> Result<Vec<SourceMessage>, IngestError>;
+    /// Acknowledge messages whose events have been recorded
+    async fn ack(&self, tokens: Vec<AckToken>) -> Result<(), IngestError>;
+}
+
+/// Ingestion tuning
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    /// Messages requested per poll
+    pub poll_batch: usize,
+    /// Longest a poll waits for messages
+    pub poll_wait: Duration,
+    /// Events per `MeteringEngine::record_batch` call
+    pub record_batch: usize,
+    /// Flush a partial batch after this long
+    pub flush_interval: Duration,
+    /// Messages buffered between consumer and recorder
+    pub queue_capacity: usize,
+    /// How long event IDs are remembered for dedup
+    pub dedup_window: Duration,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            poll_batch: 500,
+            poll_wait: Duration::from_secs(1),
+            record_batch: 5_000,
+            flush_interval: Duration::from_millis(500),
+            queue_capacity: 20_000,
+            dedup_window: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Recently seen event IDs. Two generations rotate every half window, so
+/// an ID is remembered for between half and the full window.
+#[derive(Debug)]
+pub struct DedupWindow {
+    current: HashSet<String>,
+    previous: HashSet<String>,
+    rotated_at: Instant,
+    half: Duration,
+}
+
+impl DedupWindow {
+    /// Create window
+    pub fn new(window: Duration) -> Self {
+        Self {
+            current: HashSet::new(),
+            previous: HashSet::new(),
+            rotated_at: Instant::now(),
+            half: window / 2,
+        }
+    }
+
+    /// Record an ID; false if it was already seen
+    pub fn insert(&mut self, id: &str, now: Instant) -> bool {
+        if now.duration_since(self.rotated_at) >= self.half {
+            self.previous = std::mem::take(&mut self.current);
+            self.rotated_at = now;
+        }
+        if self.previous.contains(id) {
+            return false;
+        }
+        self.current.insert(id.to_string())
+    }
+
+    /// IDs currently remembered
+    pub fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    /// Whether no IDs are remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Ingestion counters
+#[derive(Debug, Default)]
+struct IngestStats {
+    received: AtomicU64,
+    recorded: AtomicU64,
+    duplicates: AtomicU64,
+    malformed: AtomicU64,
+    batches: AtomicU64,
+    backpressure_waits: AtomicU64,
+    source_errors: AtomicU64,
+}
+
+/// Point-in-time ingestion counters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IngestStatsSnapshot {
+    /// Messages pulled from the source
+    pub received: u64,
+    /// Events recorded in the metering engine
+    pub recorded: u64,
+    /// Events dropped as redeliveries
+    pub duplicates: u64,
+    /// Messages that could not be decoded (acknowledged and dropped)
+    pub malformed: u64,
+    /// Batches written to the metering engine
+    pub batches: u64,
+    /// Times the consumer blocked on a full queue
+    pub backpressure_waits: u64,
+    /// Failed polls or acks
+    pub source_errors: u64,
+}
+
+impl IngestStats {
+    fn snapshot(&self) -> IngestStatsSnapshot {
+        IngestStatsSnapshot {
+            received: self.received.load(Ordering::Relaxed),
+            recorded: self.recorded.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            batches: self.batches.load(Ordering::Relaxed),
+            backpressure_waits: self.backpressure_waits.load(Ordering::Relaxed),
+            source_errors: self.source_errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Decoded message on its way to the recorder
+struct Decoded {
+    events: Vec<UsageEvent>,
+    ack: AckToken,
+}
+
+/// Streaming ingestion pipeline into the metering engine
+pub struct UsageIngestor {
+    engine: Arc<MeteringEngine>,
+    source: Arc<dyn UsageSource>,
+    config: IngestConfig,
+}
+
+impl UsageIngestor {
+    /// Create ingestor
+    pub fn new(engine: Arc<MeteringEngine>, source: Arc<dyn UsageSource>) -> Self {
+        Self {
+            engine,
+            source,
+            config: IngestConfig::default(),
+        }
+    }
+
+    /// Set tuning
+    pub fn with_config(mut self, config: IngestConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Spawn consumer and recorder tasks
+    pub fn start(self) -> IngestHandle {
+        let stats = Arc::new(IngestStats::default());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (tx, rx) = mpsc::channel(self.config.queue_capacity.max(1));
+
+        let consumer = tokio::spawn(consume(
+            self.source.clone(),
+            self.config.clone(),
+            tx,
+            shutdown_rx,
+            stats.clone(),
+        ));
+        let recorder = tokio::spawn(record(
+            self.engine,
+            self.source,
+            self.config,
+            rx,
+            stats.clone(),
+        ));
+
+        IngestHandle { shutdown: shutdown_tx, stats, consumer, recorder }
+    }
+}
+
+/// Running ingestion pipeline
+pub struct IngestHandle {
+    shutdown: watch::Sender<bool>,
+    stats: Arc<IngestStats>,
+    consumer: JoinHandle<()>,
+    recorder: JoinHandle<()>,
+}
+
+impl IngestHandle {
+    /// Current counters
+    pub fn stats(&self) -> IngestStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    /// Stop polling, drain the queue, record and acknowledge what was pulled
+    pub async fn shutdown(self) -> IngestStatsSnapshot {
+        let _ = self.shutdown.send(true);
+        let _ = self.consumer.await;
+        let _ = self.recorder.await;
+        self.stats.snapshot()
+    }
+}
+
+async fn consume(
+    source: Arc<dyn UsageSource>,
+    config: IngestConfig,
+    tx: mpsc::Sender<Decoded>,
+    mut shutdown: watch::Receiver<bool>,
+    stats: Arc<IngestStats>,
+) {
+    let mut backoff = Duration::from_millis(100);
+
+    while !*shutdown.borrow() {
+        let polled = tokio::select! {
+            polled = source.poll(config.poll_batch, config.poll_wait) => polled,
+            _ = shutdown.changed() => break,
+        };
+
+        let messages = match polled {
+            Ok(messages) => {
+                backoff = Duration::from_millis(100);
+                messages
+            }
+            Err(e) => {
+                stats.source_errors.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Usage source poll failed, retrying in {:?}: {}", backoff, e);
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = shutdown.changed() => break,
+                }
+                backoff = (backoff * 2).min(Duration::from_secs(30));
+                continue;
+            }
+        };
+
+        for message in messages {
+            stats.received.fetch_add(1, Ordering::Relaxed);
+            let decoded = decode(message, &stats);
+
+            // Full queue: block here so we stop pulling from the broker
+            match tx.try_send(decoded) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(decoded)) => {
+                    stats.backpressure_waits.fetch_add(1, Ordering::Relaxed);
+                    if tx.send(decoded).await.is_err() {
+                        return;
+                    }
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => return,
+            }
+        }
+    }
+}
+
+async fn record(
+    engine: Arc<MeteringEngine>,
+    source: Arc<dyn UsageSource>,
+    config: IngestConfig,
+    mut rx: mpsc::Receiver<Decoded>,
+    stats: Arc<IngestStats>,
+) {
+    let mut dedup = DedupWindow::new(config.dedup_window);
+    let mut events = Vec::with_capacity(config.record_batch);
+    let mut acks = Vec::new();
+    let mut ticker = tokio::time::interval(config.flush_interval);
+
+    loop {
+        tokio::select! {
+            decoded = rx.recv() => match decoded {
+                Some(decoded) => {
+                    let now = Instant::now();
+                    for event in decoded.events {
+                        let fresh = event.idempotency_key.as_deref()
+                            .is_none_or(|id| dedup.insert(id, now));
+                        if fresh {
+                            events.push(event);
+                        } else {
+                            stats.duplicates.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    acks.push(decoded.ack);
+
+                    if events.len() >= config.record_batch {
+                        flush(&engine, source.as_ref(), &mut events, &mut acks, &stats).await;
+                    }
+                }
+                None => {
+                    flush(&engine, source.as_ref(), &mut events, &mut acks, &stats).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => {
+                if !acks.is_empty() {
+                    flush(&engine, source.as_ref(), &mut events, &mut acks, &stats).await;
+                }
+            }
+        }
+    }
+}
+
+async fn flush(
+    engine: &MeteringEngine,
+    source: &dyn UsageSource,
+    events: &mut Vec<UsageEvent>,
+    acks: &mut Vec<AckToken>,
+    stats: &IngestStats,
+) {
+    if !events.is_empty() {
+        let batch = std::mem::take(events);
+        let total = batch.len() as u64;
+        let recorded = engine.record_batch(batch) as u64;
+        stats.recorded.fetch_add(recorded, Ordering::Relaxed);
+        // The engine also rejects keys it saw before the window
+        stats.duplicates.fetch_add(total - recorded, Ordering::Relaxed);
+        stats.batches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let tokens: Vec<_> = acks.drain(..).filter(|t| *t != AckToken::None).collect();
+    if !tokens.is_empty() {
+        // Unacked messages are redelivered and dropped by the dedup window
+        if let Err(e) = source.ack(tokens).await {
+            stats.source_errors.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Failed to acknowledge usage messages: {}", e);
+        }
+    }
+}
+
+/// Decode one message; malformed payloads yield no events but are still
+/// acknowledged so they don't block the stream
+fn decode(message: SourceMessage, stats: &IngestStats) -> Decoded {
+    let events = match serde_json::from_slice::<serde_json::Value>(&message.payload) {
+        Ok(serde_json::Value::Array(items)) => items.into_iter()
+            .map(serde_json::from_value::<UsageEvent>)
+            .collect::<Result<Vec<_>, _>>(),
+        Ok(value) => serde_json::from_value::<UsageEvent>(value).map(|e| vec![e]),
+        Err(e) => Err(e),
+    };
+
+    match events {
+        Ok(mut events) => {
+            let single = events.len() == 1;
+            for (i, event) in events.iter_mut().enumerate() {
+                if event.idempotency_key.is_none() {
+                    event.idempotency_key = Some(if single {
+                        message.id.clone()
+                    } else {
+                        format!("{}#{}", message.id, i)
+                    });
+                }
+            }
+            Decoded { events, ack: message.ack }
+        }
+        Err(e) => {
+            stats.malformed.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Dropping malformed usage message {}: {}", message.id, e);
+            Decoded { events: Vec::new(), ack: message.ack }
+        }
+    }
+}
+
+// =============================================================================
+// NATS JetStream
+// =============================================================================
+
+/// NATS JetStream pull consumer speaking the core NATS text protocol.
+///
+/// Each poll issues a `CONSUMER.MSG.NEXT` request for a batch; messages are
+/// acknowledged individually on their `$JS.ACK` reply subject. The consumer
+/// should be durable with explicit ack so unacked messages are redelivered
+/// after `ack_wait`.
+pub struct JetStreamSource {
+    addr: String,
+    stream: String,
+    consumer: String,
+    token: Option<String>,
+    credentials: Option<(String, String)>,
+    conn: tokio::sync::Mutex<Option<NatsConnection>>,
+}
+
+struct NatsConnection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    inbox: String,
+}
+
+impl JetStreamSource {
+    /// Create source for a durable pull consumer; `addr` is `host:port`
+    pub fn new(addr: &str, stream: &str, consumer: &str) -> Self {
+        Self {
+            addr: addr.trim_start_matches("nats://").to_string(),
+            stream: stream.to_string(),
+            consumer: consumer.to_string(),
+            token: None,
+            credentials: None,
+            conn: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Authenticate with a token
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Authenticate with user/password
+    pub fn with_credentials(mut self, user: &str, password: &str) -> Self {
+        self.credentials = Some((user.to_string(), password.to_string()));
+        self
+    }
+
+    async fn connect(&self) -> Result<NatsConnection, IngestError> {
+        let stream = TcpStream::connect(&self.addr).await
+            .map_err(|e| IngestError::Connection(format!("{}: {}", self.addr, e)))?;
+        let (read, writer) = stream.into_split();
+        let mut conn = NatsConnection {
+            reader: BufReader::new(read),
+            writer,
+            inbox: format!("_INBOX.{}", Uuid::new_v4().simple()),
+        };
+
+        let info = conn.read_line().await?;
+        if !info.starts_with("INFO ") {
+            return Err(IngestError::Protocol(format!("expected INFO, got {}", info)));
+        }
+
+        let mut options = serde_json::json!({
+            "verbose": false,
+            "pedantic": false,
+            "headers": true,
+            "no_responders": true,
+            "name": "sase-billing-ingest",
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(token) = &self.token {
+            options["auth_token"] = token.clone().into();
+        }
+        if let Some((user, pass)) = &self.credentials {
+            options["user"] = user.clone().into();
+            options["pass"] = pass.clone().into();
+        }
+
+        let handshake = format!("CONNECT {}\r\nPING\r\nSUB {} 1\r\n", options, conn.inbox);
+        conn.write(handshake.as_bytes()).await?;
+
+        loop {
+            let line = conn.read_line().await?;
+            match line.as_str() {
+                "PONG" => break,
+                "+OK" => {}
+                "PING" => conn.write(b"PONG\r\n").await?,
+                l if l.starts_with("-ERR") => return Err(IngestError::Connection(l.to_string())),
+                _ => {}
+            }
+        }
+
+        tracing::info!("Connected to NATS {} for {}/{}", self.addr, self.stream, self.consumer);
+        Ok(conn)
+    }
+
+    async fn fetch(&self, conn: &mut NatsConnection, max: usize, wait: Duration) -> Result<Vec<SourceMessage>, IngestError> {
+        let request = serde_json::json!({ "batch": max, "expires": wait.as_nanos() as u64 }).to_string();
+        let publish = format!(
+            "PUB $JS.API.CONSUMER.MSG.NEXT.{}.{} {} {}\r\n{}\r\n",
+            self.stream, self.consumer, conn.inbox, request.len(), request,
+        );
+        conn.write(publish.as_bytes()).await?;
+
+        let deadline = Instant::now() + wait + Duration::from_secs(1);
+        let mut messages = Vec::new();
+
+        while messages.len() < max {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = match tokio::time::timeout(remaining, conn.read_line()).await {
+                Ok(line) => line?,
+                Err(_) => break,
+            };
+            let parts: Vec<&str> = line.split_whitespace().collect();
+
+            match parts.first().copied() {
+                Some("PING") => conn.write(b"PONG\r\n").await?,
+                Some("MSG") => {
+                    // MSG <subject> <sid> [reply-to] <#bytes>
+                    let size = parse_size(parts.last())?;
+                    let payload = conn.read_payload(size).await?;
+                    let reply = if parts.len() == 5 { Some(parts[3]) } else { None };
+                    messages.push(self.message(reply, payload));
+                }
+                Some("HMSG") => {
+                    // HMSG <subject> <sid> [reply-to] <#header bytes> <#total bytes>
+                    let total = parse_size(parts.last())?;
+                    let header_len = parse_size(parts.get(parts.len().saturating_sub(2)))?;
+                    let body = conn.read_payload(total).await?;
+                    let headers = String::from_utf8_lossy(&body[..header_len.min(body.len())]);
+                    let status = headers.lines().next().unwrap_or_default();
+
+                    // 404 no messages, 408 request expired, 409 consumer issue: batch is over
+                    if status.starts_with("NATS/1.0 4") {
+                        break;
+                    }
+                    // 100 idle heartbeat / flow control
+                    if status.starts_with("NATS/1.0 1") {
+                        continue;
+                    }
+                    let reply = if parts.len() == 6 { Some(parts[3]) } else { None };
+                    messages.push(self.message(reply, body[header_len.min(body.len())..].to_vec()));
+                }
+                Some("-ERR") => return Err(IngestError::Protocol(line)),
+                _ => {}
+            }
+        }
+
+        Ok(messages)
+    }
+
+    fn message(&self, reply: Option<&str>, payload: Vec<u8>) -> SourceMessage {
+        let (id, ack) = match reply {
+            Some(reply) => (
+                stream_sequence(reply)
+                    .map(|seq| format!("{}:{}", self.stream, seq))
+                    .unwrap_or_else(|| reply.to_string()),
+                AckToken::Subject(reply.to_string()),
+            ),
+            None => (Uuid::new_v4().to_string(), AckToken::None),
+        };
+        SourceMessage { id, payload, ack }
+    }
+}
+
+#[async_trait]
+impl UsageSource for JetStreamSource {
+    async fn poll(&self, max: usize, wait: Duration) -> Result<Vec<SourceMessage>, IngestError> {
+        let mut guard = self.conn.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.connect().await?);
+        }
+        let Some(conn) = guard.as_mut() else { return Ok(Vec::new()) };
+
+        let result = self.fetch(conn, max, wait).await;
+        if result.is_err() {
+            // Reconnect on the next poll; unacked messages are redelivered
+            *guard = None;
+        }
+        result
+    }
+
+    async fn ack(&self, tokens: Vec<AckToken>) -> Result<(), IngestError> {
+        let mut guard = self.conn.lock().await;
+        let Some(conn) = guard.as_mut() else {
+            return Err(IngestError::Connection("not connected".to_string()));
+        };
+
+        let mut buf = String::new();
+        for token in tokens {
+            if let AckToken::Subject(subject) = token {
+                buf.push_str(&format!("PUB {} 0\r\n\r\n", subject));
+            }
+        }
+        let result = conn.write(buf.as_bytes()).await;
+        if result.is_err() {
+            *guard = None;
+        }
+        result
+    }
+}
+
+impl NatsConnection {
+    async fn read_line(&mut self) -> Result<String, IngestError> {
+        let mut line = String::new();
+        let read = self.reader.read_line(&mut line).await
+            .map_err(|e| IngestError::Connection(e.to_string()))?;
+        if read == 0 {
+            return Err(IngestError::Connection("connection closed".to_string()));
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    async fn read_payload(&mut self, size: usize) -> Result<Vec<u8>, IngestError> {
+        let mut buf = vec![0u8; size + 2];
+        self.reader.read_exact(&mut buf).await
+            .map_err(|e| IngestError::Connection(e.to_string()))?;
+        buf.truncate(size);
+        Ok(buf)
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), IngestError> {
+        self.writer.write_all(data).await
+            .map_err(|e| IngestError::Connection(e.to_string()))
+    }
+}
+
+fn parse_size(token: Option<&&str>) -> Result<usize, IngestError> {
+    token.and_then(|t| t.parse().ok())
+        .ok_or_else(|| IngestError::Protocol("bad message size".to_string()))
+}
+
+/// Stream sequence from a `$JS.ACK` subject. Handles both the legacy
+/// `$JS.ACK.<stream>.<consumer>.<delivered>.<sseq>...` layout and the
+/// domain-qualified `$JS.ACK.<domain>.<hash>.<stream>.<consumer>...` one.
+fn stream_sequence(reply: &str) -> Option<u64> {
+    let tokens: Vec<&str> = reply.split('.').collect();
+    if tokens.len() < 9 || tokens[0] != "$JS" || tokens[1] != "ACK" {
+        return None;
+    }
+    let index = if tokens.len() == 9 { 5 } else { 7 };
+    tokens.get(index)?.parse().ok()
+}
+
+/// Ingestion error
+#[derive(Debug, Clone)]
+pub enum IngestError {
+    /// Broker unreachable or connection dropped
+    Connection(String),
+    /// Unexpected protocol response
+    Protocol(String),
+}
+
+impl std::fmt::Display for IngestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connection(e) => write!(f, "Usage stream connection error: {}", e),
+            Self::Protocol(e) => write!(f, "Usage stream protocol error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metering::UsageMetric;
+    use parking_lot::Mutex;
+    use std::collections::{HashMap, VecDeque};
+
+    /// Source replaying scripted polls and recording acknowledgements
+    #[derive(Default)]
+    struct ScriptedSource {
+        polls: Mutex<VecDeque<Result<Vec<SourceMessage>, IngestError>>>,
+        acked: Mutex<Vec<AckToken>>,
+    }
+
+    #[async_trait]
+    impl UsageSource for ScriptedSource {
+        async fn poll(&self, _max: usize, wait: Duration) -> Result<Vec<SourceMessage>, IngestError> {
+            let next = self.polls.lock().pop_front();
+            match next {
+                Some(polled) => polled,
+                None => {
+                    tokio::time::sleep(wait).await;
+                    Ok(Vec::new())
+                }
+            }
+        }
+
+        async fn ack(&self, tokens: Vec<AckToken>) -> Result<(), IngestError> {
+            self.acked.lock().extend(tokens);
+            Ok(())
+        }
+    }
+
+    fn event(tenant_id: Uuid, key: Option<&str>) -> serde_json::Value {
+        serde_json::to_value(UsageEvent {
+            tenant_id,
+            timestamp: chrono::Utc::now(),
+            metric: UsageMetric::APIRequests,
+            value: 1.0,
+            dimensions: HashMap::new(),
+            idempotency_key: key.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    fn message(seq: i64, payload: serde_json::Value) -> SourceMessage {
+        SourceMessage {
+            id: format!("USAGE:{}", seq),
+            payload: payload.to_string().into_bytes(),
+            ack: AckToken::Offset { partition: 0, offset: seq },
+        }
+    }
+
+    fn config() -> IngestConfig {
+        IngestConfig {
+            poll_batch: 10,
+            poll_wait: Duration::from_millis(10),
+            record_batch: 2,
+            flush_interval: Duration::from_millis(10),
+            queue_capacity: 1,
+            dedup_window: Duration::from_secs(60),
+        }
+    }
+
+    /// Run the pipeline until every offset up to `last` is acknowledged
+    async fn ingest(
+        engine: Arc<MeteringEngine>,
+        source: Arc<ScriptedSource>,
+        last: i64,
+    ) -> IngestStatsSnapshot {
+        let handle = UsageIngestor::new(engine, source.clone()).with_config(config()).start();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !source.acked.lock().contains(&AckToken::Offset { partition: 0, offset: last }) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("messages acknowledged");
+        handle.shutdown().await
+    }
+
+    #[test]
+    fn test_dedup_window_rotation() {
+        let mut dedup = DedupWindow::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(dedup.is_empty());
+        assert!(dedup.insert("a", start));
+        assert!(!dedup.insert("a", start));
+
+        // One rotation: still remembered from the previous generation
+        assert!(dedup.insert("b", start + Duration::from_secs(30)));
+        assert!(!dedup.insert("a", start + Duration::from_secs(31)));
+        assert_eq!(dedup.len(), 2);
+
+        // Two rotations: forgotten
+        assert!(dedup.insert("a", start + Duration::from_secs(61)));
+        assert!(!dedup.insert("b", start + Duration::from_secs(62)));
+    }
+
+    #[test]
+    fn test_decode_keys_events_by_message() {
+        let stats = IngestStats::default();
+        let tenant = Uuid::new_v4();
+
+        let single = decode(message(1, event(tenant, None)), &stats);
+        assert_eq!(single.events[0].idempotency_key.as_deref(), Some("USAGE:1"));
+        assert_eq!(single.ack, AckToken::Offset { partition: 0, offset: 1 });
+
+        let batch = decode(message(2, serde_json::json!([event(tenant, None), event(tenant, Some("pop-1")), event(tenant, None)])), &stats);
+        let keys: Vec<_> = batch.events.iter().map(|e| e.idempotency_key.clone().unwrap()).collect();
+        assert_eq!(keys, ["USAGE:2#0", "pop-1", "USAGE:2#2"]);
+
+        // One bad element drops the whole message, which is still acked
+        let malformed = decode(message(3, serde_json::json!([event(tenant, None), { "value": 1 }])), &stats);
+        assert!(malformed.events.is_empty());
+        assert_eq!(malformed.ack, AckToken::Offset { partition: 0, offset: 3 });
+        let garbage = SourceMessage { id: "x".to_string(), payload: b"not json".to_vec(), ack: AckToken::None };
+        assert!(decode(garbage, &stats).events.is_empty());
+        assert_eq!(stats.snapshot().malformed, 2);
+    }
+
+    #[test]
+    fn test_stream_sequence() {
+        assert_eq!(stream_sequence("$JS.ACK.USAGE.billing.1.42.7.1700000000000000000.0"), Some(42));
+        assert_eq!(stream_sequence("$JS.ACK.hub.ACCOUNTHASH.USAGE.billing.3.42.7.1700000000000000000.0.token"), Some(42));
+        assert_eq!(stream_sequence("$JS.ACK.USAGE.billing.1"), None);
+        assert_eq!(stream_sequence("_INBOX.abc.def.1.2.3.4.5.6"), None);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_records_dedups_and_acks() {
+        let tenant = Uuid::new_v4();
+        let engine = Arc::new(MeteringEngine::new());
+        let source = Arc::new(ScriptedSource::default());
+        {
+            let mut polls = source.polls.lock();
+            polls.push_back(Err(IngestError::Connection("broker restarting".to_string())));
+            polls.push_back(Ok(vec![
+                message(1, event(tenant, Some("pop-1:1"))),
+                message(2, event(tenant, None)),
+                message(3, serde_json::json!([event(tenant, None), event(tenant, None)])),
+                message(4, serde_json::json!("garbage")),
+            ]));
+            // Redeliveries of 1 and 2 after a missed ack
+            polls.push_back(Ok(vec![
+                message(1, event(tenant, Some("pop-1:1"))),
+                message(2, event(tenant, None)),
+                message(5, event(tenant, None)),
+            ]));
+        }
+
+        let stats = ingest(engine.clone(), source.clone(), 5).await;
+        assert_eq!(stats.source_errors, 1);
+        assert_eq!((stats.received, stats.recorded, stats.duplicates, stats.malformed), (7, 5, 2, 1));
+        assert!(stats.batches >= 3);
+        // Capacity 1 with a multi-message poll blocks the consumer
+        assert!(stats.backpressure_waits >= 1);
+        assert_eq!(engine.get_current_usage(tenant).api_requests, 5);
+
+        let acked: Vec<i64> = source.acked.lock().iter()
+            .filter_map(|t| match t { AckToken::Offset { offset, .. } => Some(*offset), _ => None })
+            .collect();
+        assert_eq!(acked, [1, 2, 3, 4, 1, 2, 5]);
+    }
+
+    /// NATS stand-in: completes the handshake, answers each pull with
+    /// `messages` then a 404 status, and records acked subjects
+    async fn nats(messages: Vec<(&'static str, String)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let acked = Arc::new(Mutex::new(Vec::new()));
+        let seen = acked.clone();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut reader = BufReader::new(read);
+            write.write_all(b"INFO {\"server_id\":\"test\",\"headers\":true}\r\n").await.unwrap();
+
+            let mut inbox = String::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
+                let parts: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+                line.clear();
+                match parts.first().map(String::as_str) {
+                    Some("PING") => write.write_all(b"PONG\r\n").await.unwrap(),
+                    Some("SUB") => inbox = parts[1].clone(),
+                    Some("PUB") => {
+                        reader.read_line(&mut line).await.unwrap();
+                        line.clear();
+                        if parts[1].starts_with("$JS.ACK") {
+                            seen.lock().push(parts[1].clone());
+                            continue;
+                        }
+                        let mut out = String::new();
+                        for (reply, payload) in &messages {
+                            out.push_str(&format!("MSG {} 1 {} {}\r\n{}\r\n", inbox, reply, payload.len(), payload));
+                        }
+                        out.push_str(&format!("HMSG {} 1 {} {}\r\n{}\r\n", inbox, 28, 28, "NATS/1.0 404 No Messages\r\n\r\n"));
+                        write.write_all(out.as_bytes()).await.unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        });
+        (addr, acked)
+    }
+
+    #[tokio::test]
+    async fn test_jetstream_pull_and_ack() {
+        let tenant = Uuid::new_v4();
+        let reply = "$JS.ACK.USAGE.billing.1.42.7.1700000000000000000.0";
+        let (addr, acked) = nats(vec![(reply, event(tenant, None).to_string())]).await;
+        let source = JetStreamSource::new(&format!("nats://{}", addr), "USAGE", "billing");
+
+        let messages = source.poll(10, Duration::from_millis(200)).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "USAGE:42");
+        assert_eq!(messages[0].ack, AckToken::Subject(reply.to_string()));
+        let decoded: UsageEvent = serde_json::from_slice(&messages[0].payload).unwrap();
+        assert_eq!(decoded.tenant_id, tenant);
+
+        source.ack(vec![messages[0].ack.clone(), AckToken::None]).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while acked.lock().is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(acked.lock().as_slice(), [reply.to_string()]);
+    }
+}
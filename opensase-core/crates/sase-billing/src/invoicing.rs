@@ -7,16 +7,20 @@ use parking_lot::RwLock;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use uuid::Uuid;
-use chrono::{NaiveDate, Utc};
+use chrono::NaiveDate;
 
-use crate::{BillingError, credits::Credit, subscriptions::Subscription, metering::MonthlyUsage};
+use crate::{BillingError, credits::{Credit, DiscountType, PromoRedemption}, subscriptions::Subscription, metering::MonthlyUsage};
 use crate::pricing::{PricingEngine, LineItem};
+use crate::sandbox::SandboxRegistry;
+use crate::tax::{TaxService, TaxBreakdown, TaxableLine, TaxCategory};
 
 /// Invoice generator
 pub struct InvoiceGenerator {
     pricing: Arc<PricingEngine>,
     invoices: Arc<RwLock<HashMap<Uuid, Invoice>>>,
     sequence: Arc<RwLock<u64>>,
+    sandbox: Arc<SandboxRegistry>,
+    tax: Arc<TaxService>,
 }
 
 impl InvoiceGenerator {
@@ -25,37 +29,66 @@ impl InvoiceGenerator {
             pricing,
             invoices: Arc::new(RwLock::new(HashMap::new())),
             sequence: Arc::new(RwLock::new(1000)),
+            sandbox: Arc::new(SandboxRegistry::new()),
+            tax: Arc::new(TaxService::new()),
         }
     }
 
+    /// Use shared sandbox registry (simulated clocks, test invoice marking)
+    pub fn with_sandbox(mut self, sandbox: Arc<SandboxRegistry>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Use shared tax service (tenant addresses, exemptions, provider)
+    pub fn with_tax(mut self, tax: Arc<TaxService>) -> Self {
+        self.tax = tax;
+        self
+    }
+
     /// Generate invoice
+    ///
+    /// `discounts` are the subscription's redeemed promo codes; each active
+    /// one becomes a discount line.
     pub fn generate(
         &self,
         tenant_id: Uuid,
         subscription: &Subscription,
         usage: &MonthlyUsage,
         credits: &[Credit],
+        discounts: &[PromoRedemption],
     ) -> Result<Invoice, BillingError> {
-        let pricing = self.pricing.calculate(tenant_id, &subscription.plan_id, usage);
+        // Base charge stays on the plan the period started with; mid-period
+        // changes are billed through the proration items below
+        let plan_id = subscription.billed_plan();
+        let pricing = self.pricing.calculate(tenant_id, plan_id, usage);
         if !pricing.success {
             return Err(BillingError::Invoice(pricing.error.unwrap_or_default()));
         }
 
+        let sandbox = subscription.sandbox || self.sandbox.is_sandbox(tenant_id);
+        let now = self.sandbox.now(tenant_id);
+
         // Generate invoice number
         let invoice_number = {
             let mut seq = self.sequence.write();
             *seq += 1;
-            format!("INV-{:06}", *seq)
+            if sandbox {
+                format!("TEST-INV-{:06}", *seq)
+            } else {
+                format!("INV-{:06}", *seq)
+            }
         };
 
         // Build line items
         let mut items = vec![
             InvoiceLineItem {
-                description: format!("{} Plan - Monthly", subscription.plan_id),
+                description: format!("{} Plan - Monthly", plan_id),
                 quantity: 1.0,
                 unit_price: pricing.base_price,
                 amount: pricing.base_price,
                 item_type: ItemType::Subscription,
+                tax_amount: dec!(0),
             }
         ];
 
@@ -67,13 +100,62 @@ impl InvoiceGenerator {
                 unit_price: line.unit_price,
                 amount: line.amount,
                 item_type: ItemType::Usage,
+                tax_amount: dec!(0),
+            });
+        }
+
+        // Add proration line items (credits are negative)
+        let mut proration_total = dec!(0);
+        for proration in &subscription.pending_prorations {
+            items.push(InvoiceLineItem {
+                description: proration.description.clone(),
+                quantity: 1.0,
+                unit_price: proration.amount,
+                amount: proration.amount,
+                item_type: ItemType::Proration,
+                tax_amount: dec!(0),
+            });
+            proration_total += proration.amount;
+        }
+
+        // A net proration credit larger than the invoice carries forward
+        let carried_credit = (-(pricing.total + proration_total)).max(dec!(0));
+        let mut remaining = (pricing.total + proration_total).max(dec!(0));
+
+        // Promo discounts: percentages first, then fixed amounts, each taken
+        // from what the earlier ones left so stacking never goes below zero
+        let mut promo_discount = dec!(0);
+        let mut discount_items = Vec::new();
+        let (percentages, fixed): (Vec<_>, Vec<_>) = discounts.iter()
+            .filter(|d| d.is_active())
+            .partition(|d| matches!(d.discount_type, DiscountType::Percentage(_)));
+        for redemption in percentages.into_iter().chain(fixed) {
+            let amount = redemption.discount_on(remaining);
+            if amount <= dec!(0) {
+                continue;
+            }
+            remaining -= amount;
+            promo_discount += amount;
+
+            items.push(InvoiceLineItem {
+                description: redemption.description(),
+                quantity: 1.0,
+                unit_price: -amount,
+                amount: -amount,
+                item_type: ItemType::Discount,
+                tax_amount: dec!(0),
+            });
+            discount_items.push(DiscountApplication {
+                redemption_id: redemption.id,
+                code: redemption.code.clone(),
+                amount,
             });
         }
+        let subtotal = pricing.subtotal + proration_total - promo_discount;
 
         // Apply credits
         let mut credits_applied = dec!(0);
         let mut credit_items = Vec::new();
-        let mut remaining = pricing.total;
 
         for credit in credits.iter().filter(|c| c.is_active()) {
             if remaining <= dec!(0) {
@@ -90,10 +172,34 @@ impl InvoiceGenerator {
             });
         }
 
-        // Calculate tax (placeholder - integrate with Avalara)
-        let tax_rate = dec!(0.0875); // 8.75% example
-        let taxable = remaining;
-        let tax_amount = taxable * tax_rate;
+        // Calculate tax per line on the amount actually billed: discount and
+        // credits are spread across charge lines in proportion to their amount
+        let charged: Decimal = items.iter().map(|i| i.amount).filter(|a| *a > dec!(0)).sum();
+        let factor = if charged > dec!(0) { (remaining / charged).min(dec!(1)) } else { dec!(0) };
+        let taxable_lines = items.iter().enumerate()
+            .map(|(index, item)| TaxableLine {
+                index,
+                description: item.description.clone(),
+                amount: (item.amount * factor).round_dp(2),
+                category: if item.amount > dec!(0) { TaxCategory::DigitalService } else { TaxCategory::NonTaxable },
+            })
+            .collect();
+
+        let tax_breakdown = match self.tax.calculate(tenant_id, now.date_naive(), "USD", taxable_lines) {
+            Ok(breakdown) => breakdown,
+            Err(crate::tax::TaxError::NoProfile) => {
+                tracing::warn!("No tax profile for tenant {}, invoicing without tax", tenant_id);
+                TaxBreakdown::untaxed("No billing address on file; tax not calculated")
+            }
+            Err(e) => return Err(BillingError::Tax(e.to_string())),
+        };
+
+        for (index, item) in items.iter_mut().enumerate() {
+            item.tax_amount = tax_breakdown.line_tax(index);
+        }
+
+        let tax_amount = tax_breakdown.total_tax;
+        let tax_rate = if remaining > dec!(0) { (tax_amount / remaining).round_dp(4) } else { dec!(0) };
 
         let total = remaining + tax_amount;
 
@@ -106,17 +212,21 @@ impl InvoiceGenerator {
             period_end: usage.month + chrono::Duration::days(30),
             status: InvoiceStatus::Draft,
             line_items: items,
-            subtotal: pricing.subtotal,
-            discount: pricing.discount,
+            subtotal,
+            discount: pricing.discount + promo_discount,
+            discount_details: discount_items,
             credits_applied,
             credit_details: credit_items,
             tax_rate,
             tax_amount,
             total,
             currency: "USD".into(),
-            due_date: Utc::now().naive_utc().date() + chrono::Duration::days(30),
-            created_at: Utc::now(),
+            due_date: now.naive_utc().date() + chrono::Duration::days(30),
+            created_at: now,
             paid_at: None,
+            sandbox,
+            carried_credit,
+            tax_breakdown: Some(tax_breakdown),
         };
 
         self.invoices.write().insert(invoice.id, invoice.clone());
@@ -158,7 +268,7 @@ impl InvoiceGenerator {
             .ok_or_else(|| BillingError::Invoice("Invoice not found".into()))?;
         
         invoice.status = InvoiceStatus::Paid;
-        invoice.paid_at = Some(Utc::now());
+        invoice.paid_at = Some(self.sandbox.now(invoice.tenant_id));
 
         Ok(invoice.clone())
     }
@@ -182,6 +292,9 @@ pub struct Invoice {
     pub line_items: Vec<InvoiceLineItem>,
     pub subtotal: Decimal,
     pub discount: Decimal,
+    /// Promo code discount lines
+    #[serde(default)]
+    pub discount_details: Vec<DiscountApplication>,
     pub credits_applied: Decimal,
     pub credit_details: Vec<CreditApplication>,
     pub tax_rate: Decimal,
@@ -191,6 +304,16 @@ pub struct Invoice {
     pub due_date: NaiveDate,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub paid_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Sandbox (test mode) invoice; never counts as revenue
+    #[serde(default)]
+    pub sandbox: bool,
+    /// Net proration credit exceeding the invoice, carried to the tenant's
+    /// credit balance
+    #[serde(default)]
+    pub carried_credit: Decimal,
+    /// Per-line and per-jurisdiction tax detail
+    #[serde(default)]
+    pub tax_breakdown: Option<TaxBreakdown>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -209,6 +332,9 @@ pub struct InvoiceLineItem {
     pub unit_price: Decimal,
     pub amount: Decimal,
     pub item_type: ItemType,
+    /// Tax charged on this line
+    #[serde(default)]
+    pub tax_amount: Decimal,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -217,6 +343,10 @@ pub enum ItemType {
     Usage,
     Credit,
     Adjustment,
+    /// Mid-cycle plan change credit or charge
+    Proration,
+    /// Promo code discount
+    Discount,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -225,3 +355,145 @@ pub struct CreditApplication {
     pub description: String,
     pub amount: Decimal,
 }
+
+/// Promo discount taken on an invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountApplication {
+    /// Redemption the discount came from
+    pub redemption_id: Uuid,
+    /// Promo code
+    pub code: String,
+    /// Amount taken off
+    pub amount: Decimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credits::CreditManager;
+    use crate::subscriptions::{BillingPeriod, ProrationItem, SubscriptionManager};
+    use crate::tax::{BillingAddress, TaxProfile};
+
+    fn usage(tenant_id: Uuid) -> MonthlyUsage {
+        MonthlyUsage {
+            tenant_id,
+            month: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            total_bandwidth_ingress_gb: 0.0,
+            total_bandwidth_egress_gb: 0.0,
+            peak_users: 0,
+            peak_devices: 0,
+            total_security_events: 0,
+            total_api_requests: 0,
+            total_rbi_session_minutes: 0.0,
+            total_rbi_streamed_gb: 0.0,
+            peak_rbi_sessions: 0,
+        }
+    }
+
+    fn proration(amount: Decimal) -> ProrationItem {
+        let now = chrono::Utc::now();
+        ProrationItem {
+            id: Uuid::new_v4(),
+            plan_id: "pro".into(),
+            description: "Plan change".into(),
+            fraction: dec!(0.5),
+            amount,
+            period_start: now,
+            period_end: now,
+            created_at: now,
+        }
+    }
+
+    fn subscription(tenant_id: Uuid, plan_id: &str, prorations: Vec<Decimal>) -> Subscription {
+        let mut sub = SubscriptionManager::new().create(tenant_id, plan_id, BillingPeriod::Monthly);
+        sub.pending_prorations = prorations.into_iter().map(proration).collect();
+        sub
+    }
+
+    fn generator() -> InvoiceGenerator {
+        InvoiceGenerator::new(Arc::new(PricingEngine::new()))
+    }
+
+    #[test]
+    fn test_prorations_are_invoice_lines() {
+        let tenant_id = Uuid::new_v4();
+        let sub = subscription(tenant_id, "pro", vec![dec!(-49.50), dec!(249.50)]);
+        let invoice = generator().generate(tenant_id, &sub, &usage(tenant_id), &[], &[]).unwrap();
+
+        let prorations: Vec<Decimal> = invoice.line_items.iter()
+            .filter(|i| matches!(i.item_type, ItemType::Proration))
+            .map(|i| i.amount)
+            .collect();
+        assert_eq!(prorations, vec![dec!(-49.50), dec!(249.50)]);
+        assert_eq!(invoice.subtotal, dec!(299.00));
+        assert_eq!(invoice.total, dec!(299.00));
+        assert_eq!(invoice.carried_credit, dec!(0));
+    }
+
+    #[test]
+    fn test_net_credit_beyond_the_invoice_is_carried() {
+        let tenant_id = Uuid::new_v4();
+        let sub = subscription(tenant_id, "pro", vec![dec!(-150.00)]);
+        let invoice = generator().generate(tenant_id, &sub, &usage(tenant_id), &[], &[]).unwrap();
+
+        assert_eq!(invoice.subtotal, dec!(-51.00));
+        assert_eq!(invoice.total, dec!(0));
+        assert_eq!(invoice.tax_amount, dec!(0));
+        assert_eq!(invoice.carried_credit, dec!(51.00));
+    }
+
+    #[test]
+    fn test_zero_amount_invoice() {
+        let tenant_id = Uuid::new_v4();
+        let sub = subscription(tenant_id, "free", vec![]);
+        let invoice = generator().generate(tenant_id, &sub, &usage(tenant_id), &[], &[]).unwrap();
+
+        assert_eq!(invoice.line_items.len(), 1);
+        assert_eq!(invoice.total, dec!(0));
+        assert_eq!(invoice.tax_rate, dec!(0));
+        assert_eq!(invoice.carried_credit, dec!(0));
+    }
+
+    #[test]
+    fn test_tax_on_amount_after_credits() {
+        let tenant_id = Uuid::new_v4();
+        let tax = Arc::new(TaxService::new());
+        tax.set_profile(TaxProfile {
+            tenant_id,
+            address: BillingAddress {
+                line1: "Hauptstrasse 1".into(),
+                city: "Berlin".into(),
+                region: None,
+                postal_code: "10115".into(),
+                country: "DE".into(),
+            },
+            tax_id: None,
+            business: false,
+            exemptions: Vec::new(),
+        });
+        let credits = CreditManager::new();
+        credits.issue_signup_credit(tenant_id, dec!(100));
+        let generator = generator().with_tax(tax);
+
+        // 299 billed less a 100 credit: 199 spread over the two charges,
+        // the proration credit itself is not taxed
+        let sub = subscription(tenant_id, "pro", vec![dec!(-49.50), dec!(249.50)]);
+        let invoice = generator.generate(tenant_id, &sub, &usage(tenant_id), &credits.get_available(tenant_id), &[]).unwrap();
+        let breakdown = invoice.tax_breakdown.as_ref().unwrap();
+        let taxed: Vec<Decimal> = breakdown.lines.iter().map(|l| l.taxable_amount).collect();
+        assert_eq!(taxed, vec![dec!(56.53), dec!(142.47)]);
+        assert_eq!(invoice.credits_applied, dec!(100));
+        assert_eq!(invoice.line_items[1].tax_amount, dec!(0));
+        assert_eq!(invoice.tax_amount, dec!(37.81));
+        assert_eq!(invoice.total, dec!(236.81));
+        let line_taxes: Decimal = invoice.line_items.iter().map(|i| i.tax_amount).sum();
+        assert_eq!(line_taxes, invoice.tax_amount);
+
+        // Credit covering the whole invoice leaves nothing to tax
+        credits.issue_signup_credit(tenant_id, dec!(500));
+        let sub = subscription(tenant_id, "pro", vec![]);
+        let invoice = generator.generate(tenant_id, &sub, &usage(tenant_id), &credits.get_available(tenant_id), &[]).unwrap();
+        assert_eq!(invoice.tax_amount, dec!(0));
+        assert_eq!(invoice.total, dec!(0));
+    }
+}
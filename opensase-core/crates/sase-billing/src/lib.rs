@@ -34,31 +34,59 @@ pub mod invoicing;
 pub mod payments;
 pub mod subscriptions;
 pub mod credits;
+pub mod sandbox;
+pub mod ingest;
+pub mod outbox;
+pub mod tax;
+pub mod dunning;
+pub mod export;
+pub mod revenue;
 
 use std::sync::Arc;
 use parking_lot::RwLock;
 use thiserror::Error;
 use rust_decimal::Decimal;
 use uuid::Uuid;
+use chrono::Datelike;
 
 pub use metering::{MeteringEngine, UsageEvent, UsageMetric};
 pub use pricing::{PricingEngine, Plan, PricingTier};
 pub use invoicing::{InvoiceGenerator, Invoice};
 pub use payments::{PaymentProcessor, PaymentMethod};
-pub use subscriptions::{SubscriptionManager, Subscription};
-pub use credits::{CreditManager, Credit};
+pub use subscriptions::{SubscriptionManager, Subscription, PlanChange, ProrationConfig, ProrationRounding};
+pub use credits::{CreditManager, Credit, PromoCode, PromoRedemption, PromoDuration};
+pub use sandbox::{SandboxRegistry, SandboxTenant};
+pub use ingest::{UsageIngestor, UsageSource, IngestConfig, IngestHandle};
+pub use outbox::{UsageSink, UsageOutbox};
+pub use dunning::{DunningConfig, DunningEvent, DunningNotifier, DunningState};
+pub use export::{UsageExporter, ExportConfig, ExportRequest, ExportFormat, Granularity, ExportJob, ObjectStore, S3Store};
+pub use revenue::{RevenueRecognizer, RecognitionSchedule, Modification, JournalEntry, JournalFormat, AccountCodes};
+pub use tax::{TaxService, TaxProvider, TaxProfile, TaxBreakdown, BillingAddress, ExemptionCertificate};
 
 /// Billing error types
 #[derive(Debug, Error)]
 pub enum BillingError {
+    /// Usage could not be recorded or aggregated
     #[error("metering error: {0}")]
     Metering(String),
+    /// Plan or price lookup failed
     #[error("pricing error: {0}")]
     Pricing(String),
+    /// Payment provider rejected or failed a charge
     #[error("payment error: {0}")]
     Payment(String),
+    /// Invoice could not be generated or updated
     #[error("invoice error: {0}")]
     Invoice(String),
+    /// Sandbox clock or enrollment misuse
+    #[error("sandbox error: {0}")]
+    Sandbox(String),
+    /// Tax could not be calculated
+    #[error("tax error: {0}")]
+    Tax(String),
+    /// Promo code could not be redeemed
+    #[error("promotion error: {0}")]
+    Promotion(String),
 }
 
 /// Revenue Platform
@@ -75,19 +103,38 @@ pub struct RevenuePlatform {
     pub subscriptions: Arc<SubscriptionManager>,
     /// Credit manager
     pub credits: Arc<CreditManager>,
+    /// Sandbox tenants and simulated clocks
+    pub sandbox: Arc<SandboxRegistry>,
+    /// Tax profiles and calculation
+    pub tax: Arc<TaxService>,
+    /// Deferred revenue schedules and recognition journal
+    pub revenue: Arc<RevenueRecognizer>,
 }
 
 impl RevenuePlatform {
     /// Create new revenue platform
     pub fn new() -> Self {
+        Self::with_stripe_keys(payments::StripeKeys::default())
+    }
+
+    /// Create revenue platform with Stripe live/test keys
+    pub fn with_stripe_keys(keys: payments::StripeKeys) -> Self {
         let pricing = Arc::new(PricingEngine::new());
+        let sandbox = Arc::new(SandboxRegistry::new());
+        let tax = Arc::new(TaxService::new());
+        let payments = Arc::new(PaymentProcessor::new().with_stripe_keys(keys).with_sandbox(sandbox.clone()));
+        let subscriptions = Arc::new(SubscriptionManager::new().with_sandbox(sandbox.clone()).with_pricing(pricing.clone()));
+        payments.dunning().add_notifier(subscriptions.clone());
         Self {
             metering: Arc::new(MeteringEngine::new()),
             pricing: pricing.clone(),
-            invoicing: Arc::new(InvoiceGenerator::new(pricing.clone())),
-            payments: Arc::new(PaymentProcessor::new()),
-            subscriptions: Arc::new(SubscriptionManager::new()),
+            invoicing: Arc::new(InvoiceGenerator::new(pricing.clone()).with_sandbox(sandbox.clone()).with_tax(tax.clone())),
+            payments,
+            subscriptions,
             credits: Arc::new(CreditManager::new()),
+            sandbox,
+            tax,
+            revenue: Arc::new(RevenueRecognizer::new()),
         }
     }
 
@@ -96,14 +143,120 @@ impl RevenuePlatform {
         self.metering.record(event);
     }
 
+    /// Start streaming usage ingestion from a PoP event stream
+    pub fn start_usage_ingestion(&self, source: Arc<dyn UsageSource>, config: IngestConfig) -> IngestHandle {
+        UsageIngestor::new(self.metering.clone(), source)
+            .with_config(config)
+            .start()
+    }
+
     /// Generate monthly invoice for tenant
     pub fn generate_invoice(&self, tenant_id: Uuid, month: chrono::NaiveDate) -> Result<Invoice, BillingError> {
         let usage = self.metering.get_monthly_usage(tenant_id, month);
         let subscription = self.subscriptions.get_active(tenant_id)
             .ok_or_else(|| BillingError::Invoice("No active subscription".into()))?;
         let credits = self.credits.get_available(tenant_id);
+        let discounts = self.credits.pending_discounts(subscription.id);
         
-        self.invoicing.generate(tenant_id, &subscription, &usage, &credits)
+        let invoice = self.invoicing.generate(tenant_id, &subscription, &usage, &credits, &discounts)?;
+        self.settle_invoice(&subscription, &invoice);
+        Ok(invoice)
+    }
+
+    /// Change a tenant's plan, prorating the current period
+    pub fn change_plan(&self, subscription_id: Uuid, new_plan_id: &str) -> Result<PlanChange, BillingError> {
+        self.subscriptions.change_plan(subscription_id, new_plan_id, true)
+            .map_err(|e| BillingError::Pricing(e.to_string()))
+    }
+
+    /// Redeem a promo code against a subscription; the discount appears on
+    /// its next invoice
+    pub fn redeem_promo_code(&self, subscription_id: Uuid, code: &str) -> Result<PromoRedemption, BillingError> {
+        let subscription = self.subscriptions.get(subscription_id)
+            .ok_or_else(|| BillingError::Promotion("Subscription not found".into()))?;
+        let now = self.sandbox.now(subscription.tenant_id);
+        self.credits.redeem_promo_code(code, &subscription, now)
+            .map_err(|e| BillingError::Promotion(e.to_string()))
+    }
+
+    /// Mark a subscription's prorations and discounts invoiced, schedule the
+    /// invoice's revenue and carry any excess credit
+    fn settle_invoice(&self, subscription: &Subscription, invoice: &Invoice) {
+        let invoiced: Vec<Uuid> = subscription.pending_prorations.iter().map(|p| p.id).collect();
+        if !invoiced.is_empty() {
+            self.subscriptions.clear_prorations(subscription.id, &invoiced);
+        }
+        self.credits.record_discounts(invoice);
+        self.revenue.schedule_invoice(invoice);
+        if invoice.carried_credit > Decimal::ZERO {
+            self.credits.issue_proration_credit(invoice.tenant_id, &invoice.invoice_number, invoice.carried_credit);
+        }
+    }
+
+    /// Charge an invoice to the tenant's default payment method
+    ///
+    /// A declined charge enters dunning: the subscription goes past due and
+    /// retries run from [`RevenuePlatform::run_dunning`].
+    pub async fn pay_invoice(&self, invoice_id: Uuid) -> Result<Invoice, BillingError> {
+        let invoice = self.invoicing.get(invoice_id)
+            .ok_or_else(|| BillingError::Invoice("Invoice not found".into()))?;
+        let payment = self.payments.process_payment(invoice.tenant_id, invoice.id, invoice.total).await
+            .map_err(|e| BillingError::Payment(e.to_string()))?;
+        let intent = payment.stripe_payment_intent_id.unwrap_or_default();
+        self.invoicing.mark_paid(invoice.id, &intent)
+    }
+
+    /// Run due payment retries; invoices recovered by a retry are marked paid
+    pub async fn run_dunning(&self) -> Vec<DunningState> {
+        let pending = self.payments.dunning().active();
+        let remaining = self.payments.run_dunning().await;
+
+        for state in pending.iter().filter(|p| !remaining.iter().any(|r| r.tenant_id == p.tenant_id)) {
+            let payment = self.payments.get_payments(state.tenant_id)
+                .into_iter()
+                .find(|p| p.invoice_id == state.invoice_id && p.status == payments::PaymentStatus::Succeeded);
+            if let Some(payment) = payment {
+                let intent = payment.stripe_payment_intent_id.unwrap_or_default();
+                if let Err(e) = self.invoicing.mark_paid(state.invoice_id, &intent) {
+                    tracing::warn!("Failed to mark recovered invoice {} paid: {}", state.invoice_id, e);
+                }
+            }
+        }
+        remaining
+    }
+
+    /// Flag tenant as sandbox (simulated clock, Stripe test mode, no revenue)
+    pub fn enroll_sandbox(&self, tenant_id: Uuid) -> SandboxTenant {
+        self.sandbox.enroll(tenant_id)
+    }
+
+    /// Fast-forward a sandbox tenant's clock and bill every cycle that elapsed
+    pub fn advance_clock(&self, tenant_id: Uuid, by: chrono::Duration) -> Result<Vec<Invoice>, BillingError> {
+        let now = self.sandbox.advance(tenant_id, by)
+            .map_err(|e| BillingError::Sandbox(e.to_string()))?;
+        self.run_billing_cycles(tenant_id, now)
+    }
+
+    /// Invoice every subscription period for the tenant that ended by `now`
+    pub fn run_billing_cycles(&self, tenant_id: Uuid, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<Invoice>, BillingError> {
+        let mut invoices = Vec::new();
+        for cycle in self.subscriptions.advance_periods(tenant_id, now) {
+            let start = cycle.period_start.date_naive();
+            let month = start.with_day(1).unwrap_or(start);
+            let usage = self.metering.get_monthly_usage(tenant_id, month);
+            let credits = self.credits.get_available(tenant_id);
+            let discounts = self.credits.pending_discounts(cycle.subscription.id);
+            let invoice = self.invoicing.generate(tenant_id, &cycle.subscription, &usage, &credits, &discounts)?;
+            self.settle_invoice(&cycle.subscription, &invoice);
+            invoices.push(invoice);
+        }
+        Ok(invoices)
+    }
+
+    /// Month-end close: recognize deferred revenue for months ended by
+    /// `through`
+    pub fn close_revenue(&self, through: chrono::NaiveDate) -> Vec<JournalEntry> {
+        self.revenue.recognize_through(through)
     }
 
     /// Get MRR (Monthly Recurring Revenue)
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use uuid::Uuid;
-use chrono::{DateTime, Utc, NaiveDate, Datelike};
+use chrono::{DateTime, Utc, NaiveDate, NaiveDateTime, Datelike};
 
 /// Metering engine for usage collection
 pub struct MeteringEngine {
@@ -42,19 +42,48 @@ impl MeteringEngine {
         // Store raw event
         self.events.write().push(event.clone());
 
+        Self::aggregate(&mut self.hourly.write(), &mut self.daily.write(), &event);
+    }
+
+    /// Record a batch under one acquisition of each lock. Duplicates by
+    /// idempotency key are skipped; returns the number recorded.
+    pub fn record_batch(&self, batch: Vec<UsageEvent>) -> usize {
+        let mut processed = self.processed.write();
+        let mut events = self.events.write();
+        let mut hourly = self.hourly.write();
+        let mut daily = self.daily.write();
+        let mut recorded = 0;
+
+        for event in batch {
+            if let Some(ref id) = event.idempotency_key {
+                if !processed.insert(id.clone()) {
+                    continue;
+                }
+            }
+            Self::aggregate(&mut hourly, &mut daily, &event);
+            events.push(event);
+            recorded += 1;
+        }
+
+        recorded
+    }
+
+    fn aggregate(
+        hourly: &mut HashMap<(Uuid, String), AggregatedUsage>,
+        daily: &mut HashMap<(Uuid, NaiveDate), DailyUsage>,
+        event: &UsageEvent,
+    ) {
         // Update hourly aggregation
         let hour_key = event.timestamp.format("%Y-%m-%d-%H").to_string();
         let key = (event.tenant_id, hour_key);
-        let mut hourly = self.hourly.write();
         let agg = hourly.entry(key).or_insert_with(|| AggregatedUsage::new(event.tenant_id));
-        agg.add(&event);
+        agg.add(event);
 
         // Update daily aggregation
         let date = event.timestamp.date_naive();
         let daily_key = (event.tenant_id, date);
-        let mut daily = self.daily.write();
         let day_agg = daily.entry(daily_key).or_insert_with(|| DailyUsage::new(event.tenant_id, date));
-        day_agg.add(&event);
+        day_agg.add(event);
     }
 
     /// Get monthly usage for tenant
@@ -71,6 +100,33 @@ impl MeteringEngine {
         usage
     }
 
+    /// Hourly aggregates for a tenant on days `from..=to`, oldest first,
+    /// keyed by the start of the hour
+    pub fn hourly_usage(&self, tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Vec<(DateTime<Utc>, AggregatedUsage)> {
+        let hourly = self.hourly.read();
+        let mut usage: Vec<_> = hourly.iter()
+            .filter(|((tid, _), _)| *tid == tenant_id)
+            .filter_map(|((_, hour), agg)| {
+                let start = NaiveDateTime::parse_from_str(&format!("{}:00", hour), "%Y-%m-%d-%H:%M").ok()?;
+                let date = start.date();
+                (date >= from && date <= to).then(|| (start.and_utc(), agg.clone()))
+            })
+            .collect();
+        usage.sort_by_key(|(start, _)| *start);
+        usage
+    }
+
+    /// Daily aggregates for a tenant on days `from..=to`, oldest first
+    pub fn daily_usage(&self, tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Vec<DailyUsage> {
+        let daily = self.daily.read();
+        let mut usage: Vec<_> = daily.iter()
+            .filter(|((tid, date), _)| *tid == tenant_id && *date >= from && *date <= to)
+            .map(|(_, day)| day.clone())
+            .collect();
+        usage.sort_by_key(|day| day.date);
+        usage
+    }
+
     /// Get current usage (for real-time display)
     pub fn get_current_usage(&self, tenant_id: Uuid) -> CurrentUsage {
         let today = Utc::now().date_naive();
@@ -86,6 +142,9 @@ impl MeteringEngine {
             active_users: day_usage.as_ref().map(|d| d.active_users).unwrap_or(0),
             active_devices: day_usage.as_ref().map(|d| d.active_devices).unwrap_or(0),
             api_requests: day_usage.as_ref().map(|d| d.api_requests).unwrap_or(0),
+            rbi_session_minutes: day_usage.as_ref().map(|d| d.rbi_session_minutes).unwrap_or(0.0),
+            rbi_streamed_gb: day_usage.as_ref().map(|d| d.rbi_streamed_gb).unwrap_or(0.0),
+            rbi_peak_sessions: day_usage.as_ref().map(|d| d.rbi_peak_sessions).unwrap_or(0),
         }
     }
 }
@@ -116,6 +175,12 @@ pub enum UsageMetric {
     SecurityEventsProcessed,
     ZTNASessions,
     APIRequests,
+    /// Browser isolation session time
+    RbiSessionMinutes,
+    /// Browser isolation pixel/DOM stream volume
+    RbiStreamedGB,
+    /// Concurrent browser isolation sessions (peak)
+    RbiPeakSessions,
 }
 
 /// Aggregated usage (hourly)
@@ -128,6 +193,15 @@ pub struct AggregatedUsage {
     pub active_devices: u64,
     pub security_events: u64,
     pub api_requests: u64,
+    /// Browser isolation session-minutes
+    #[serde(default)]
+    pub rbi_session_minutes: f64,
+    /// Browser isolation stream volume
+    #[serde(default)]
+    pub rbi_streamed_gb: f64,
+    /// Peak concurrent browser isolation sessions
+    #[serde(default)]
+    pub rbi_peak_sessions: u64,
 }
 
 impl AggregatedUsage {
@@ -140,6 +214,9 @@ impl AggregatedUsage {
             active_devices: 0,
             security_events: 0,
             api_requests: 0,
+            rbi_session_minutes: 0.0,
+            rbi_streamed_gb: 0.0,
+            rbi_peak_sessions: 0,
         }
     }
 
@@ -151,6 +228,9 @@ impl AggregatedUsage {
             UsageMetric::ActiveDevices => self.active_devices = self.active_devices.max(event.value as u64),
             UsageMetric::SecurityEventsProcessed => self.security_events += event.value as u64,
             UsageMetric::APIRequests => self.api_requests += event.value as u64,
+            UsageMetric::RbiSessionMinutes => self.rbi_session_minutes += event.value,
+            UsageMetric::RbiStreamedGB => self.rbi_streamed_gb += event.value,
+            UsageMetric::RbiPeakSessions => self.rbi_peak_sessions = self.rbi_peak_sessions.max(event.value as u64),
             _ => {}
         }
     }
@@ -169,6 +249,15 @@ pub struct DailyUsage {
     pub active_devices: u64,
     pub security_events: u64,
     pub api_requests: u64,
+    /// Browser isolation session-minutes
+    #[serde(default)]
+    pub rbi_session_minutes: f64,
+    /// Browser isolation stream volume
+    #[serde(default)]
+    pub rbi_streamed_gb: f64,
+    /// Peak concurrent browser isolation sessions
+    #[serde(default)]
+    pub rbi_peak_sessions: u64,
 }
 
 impl DailyUsage {
@@ -184,6 +273,9 @@ impl DailyUsage {
             active_devices: 0,
             security_events: 0,
             api_requests: 0,
+            rbi_session_minutes: 0.0,
+            rbi_streamed_gb: 0.0,
+            rbi_peak_sessions: 0,
         }
     }
 
@@ -201,6 +293,9 @@ impl DailyUsage {
             }
             UsageMetric::SecurityEventsProcessed => self.security_events += event.value as u64,
             UsageMetric::APIRequests => self.api_requests += event.value as u64,
+            UsageMetric::RbiSessionMinutes => self.rbi_session_minutes += event.value,
+            UsageMetric::RbiStreamedGB => self.rbi_streamed_gb += event.value,
+            UsageMetric::RbiPeakSessions => self.rbi_peak_sessions = self.rbi_peak_sessions.max(event.value as u64),
             _ => {}
         }
     }
@@ -217,6 +312,15 @@ pub struct MonthlyUsage {
     pub peak_devices: u64,
     pub total_security_events: u64,
     pub total_api_requests: u64,
+    /// Browser isolation session-minutes
+    #[serde(default)]
+    pub total_rbi_session_minutes: f64,
+    /// Browser isolation stream volume
+    #[serde(default)]
+    pub total_rbi_streamed_gb: f64,
+    /// Peak concurrent browser isolation sessions
+    #[serde(default)]
+    pub peak_rbi_sessions: u64,
 }
 
 impl MonthlyUsage {
@@ -230,6 +334,9 @@ impl MonthlyUsage {
             peak_devices: 0,
             total_security_events: 0,
             total_api_requests: 0,
+            total_rbi_session_minutes: 0.0,
+            total_rbi_streamed_gb: 0.0,
+            peak_rbi_sessions: 0,
         }
     }
 
@@ -240,6 +347,9 @@ impl MonthlyUsage {
         self.peak_devices = self.peak_devices.max(day.peak_devices);
         self.total_security_events += day.security_events;
         self.total_api_requests += day.api_requests;
+        self.total_rbi_session_minutes += day.rbi_session_minutes;
+        self.total_rbi_streamed_gb += day.rbi_streamed_gb;
+        self.peak_rbi_sessions = self.peak_rbi_sessions.max(day.rbi_peak_sessions);
     }
 }
 
@@ -253,4 +363,13 @@ pub struct CurrentUsage {
     pub active_users: u64,
     pub active_devices: u64,
     pub api_requests: u64,
+    /// Browser isolation session-minutes
+    #[serde(default)]
+    pub rbi_session_minutes: f64,
+    /// Browser isolation stream volume
+    #[serde(default)]
+    pub rbi_streamed_gb: f64,
+    /// Peak concurrent browser isolation sessions
+    #[serde(default)]
+    pub rbi_peak_sessions: u64,
 }
@@ -0,0 +1,190 @@
+//! Usage Outbox
+//!
+//! Delivery side of services that meter their own usage and push it to
+//! billing (RBI sessions, API gateway consumers, ...). Each flush appends
+//! the service's new events to a bounded backlog and submits the whole
+//! backlog to a [`UsageSink`]; a failed submission keeps it for the next
+//! flush, so an outage only delays usage. Once the backlog is full the
+//! oldest events are dropped.
+//!
+//! Redelivery relies on the events' idempotency keys: a batch that reached
+//! billing before the error was reported is not counted twice.
+
+use crate::metering::{MeteringEngine, UsageEvent};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Destination for usage events
+#[async_trait]
+pub trait UsageSink: Send + Sync {
+    /// Deliver a batch; an error leaves the batch queued for retry
+    async fn submit(&self, events: Vec<UsageEvent>) -> Result<(), String>;
+}
+
+/// In-process billing
+#[async_trait]
+impl UsageSink for MeteringEngine {
+    async fn submit(&self, events: Vec<UsageEvent>) -> Result<(), String> {
+        self.record_batch(events);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: UsageSink + ?Sized> UsageSink for Arc<T> {
+    async fn submit(&self, events: Vec<UsageEvent>) -> Result<(), String> {
+        (**self).submit(events).await
+    }
+}
+
+/// Result of one flush
+#[derive(Debug, Clone, Default)]
+pub struct FlushReport {
+    /// Events delivered to the sink
+    pub sent: usize,
+    /// Events still queued after this flush
+    pub backlog: usize,
+    /// Events dropped because the backlog was full
+    pub dropped: usize,
+    /// Sink error, when delivery failed
+    pub error: Option<String>,
+}
+
+/// Bounded, ordered backlog of usage events awaiting delivery
+pub struct UsageOutbox {
+    /// Service name used in log messages
+    service: &'static str,
+    backlog: Mutex<VecDeque<UsageEvent>>,
+    max_backlog: usize,
+    /// Serializes flushes so backlog order is preserved
+    flushing: tokio::sync::Mutex<()>,
+}
+
+impl UsageOutbox {
+    /// Outbox keeping at most `max_backlog` undelivered events
+    pub fn new(service: &'static str, max_backlog: usize) -> Self {
+        Self {
+            service,
+            backlog: Mutex::new(VecDeque::new()),
+            max_backlog,
+            flushing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Events waiting for delivery
+    pub fn len(&self) -> usize {
+        self.backlog.lock().len()
+    }
+
+    /// Nothing is waiting for delivery
+    pub fn is_empty(&self) -> bool {
+        self.backlog.lock().is_empty()
+    }
+
+    /// Queue the events returned by `drain` and deliver the backlog.
+    ///
+    /// `drain` runs while the flush lock is held, so events taken by
+    /// concurrent flushes are queued in the order they were drained.
+    pub async fn flush<F>(&self, sink: &dyn UsageSink, drain: F) -> FlushReport
+    where
+        F: FnOnce() -> Vec<UsageEvent>,
+    {
+        let _guard = self.flushing.lock().await;
+        let mut report = FlushReport::default();
+
+        let events = drain();
+        let batch: Vec<UsageEvent> = {
+            let mut backlog = self.backlog.lock();
+            backlog.extend(events);
+            while backlog.len() > self.max_backlog {
+                backlog.pop_front();
+                report.dropped += 1;
+            }
+            backlog.iter().cloned().collect()
+        };
+        if report.dropped > 0 {
+            tracing::warn!("{} usage backlog full, dropped {} oldest events", self.service, report.dropped);
+        }
+        if batch.is_empty() {
+            return report;
+        }
+
+        let count = batch.len();
+        match sink.submit(batch).await {
+            Ok(()) => {
+                self.backlog.lock().drain(..count);
+                report.sent = count;
+            }
+            Err(e) => {
+                tracing::warn!("Billing unavailable, keeping {} {} usage events for retry: {}", count, self.service, e);
+                report.error = Some(e);
+            }
+        }
+
+        report.backlog = self.len();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metering::UsageMetric;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct FlakySink {
+        received: Mutex<Vec<String>>,
+        down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl UsageSink for FlakySink {
+        async fn submit(&self, events: Vec<UsageEvent>) -> Result<(), String> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err("connection refused".into());
+            }
+            self.received.lock().extend(events.into_iter().filter_map(|e| e.idempotency_key));
+            Ok(())
+        }
+    }
+
+    fn event(key: &str) -> UsageEvent {
+        UsageEvent {
+            tenant_id: Uuid::nil(),
+            timestamp: chrono::Utc::now(),
+            metric: UsageMetric::APIRequests,
+            value: 1.0,
+            dimensions: HashMap::new(),
+            idempotency_key: Some(key.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backlog_retried_in_order_and_bounded() {
+        let outbox = UsageOutbox::new("test", 3);
+        let sink = FlakySink::default();
+        sink.down.store(true, Ordering::Relaxed);
+
+        let report = outbox.flush(&sink, || vec![event("a"), event("b")]).await;
+        assert_eq!((report.sent, report.backlog, report.dropped), (0, 2, 0));
+        assert!(report.error.is_some());
+
+        let report = outbox.flush(&sink, || vec![event("c"), event("d")]).await;
+        assert_eq!((report.backlog, report.dropped), (3, 1));
+
+        sink.down.store(false, Ordering::Relaxed);
+        let report = outbox.flush(&sink, || vec![event("e")]).await;
+        assert_eq!((report.sent, report.backlog, report.dropped), (3, 0, 1));
+        assert_eq!(*sink.received.lock(), vec!["c", "d", "e"]);
+        assert!(outbox.is_empty());
+
+        let report = outbox.flush(&sink, Vec::new).await;
+        assert_eq!(report.sent, 0);
+        assert!(report.error.is_none());
+    }
+}
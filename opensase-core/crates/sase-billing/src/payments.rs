@@ -6,7 +6,10 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 use rust_decimal::Decimal;
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Utc};
+
+use crate::dunning::{DunningConfig, DunningEngine, DunningEvent, DunningState};
+use crate::sandbox::SandboxRegistry;
 
 /// Payment processor (Stripe-based)
 pub struct PaymentProcessor {
@@ -14,8 +17,12 @@ pub struct PaymentProcessor {
     methods: Arc<RwLock<HashMap<Uuid, Vec<PaymentMethod>>>>,
     /// Payments
     payments: Arc<RwLock<HashMap<Uuid, Payment>>>,
-    /// Dunning state
-    dunning: Arc<RwLock<HashMap<Uuid, DunningState>>>,
+    /// Dunning (retries, grace period, notices)
+    dunning: Arc<DunningEngine>,
+    /// Stripe API keys per mode
+    stripe_keys: StripeKeys,
+    /// Sandbox tenants (routed to Stripe test mode)
+    sandbox: Arc<SandboxRegistry>,
 }
 
 impl PaymentProcessor {
@@ -23,7 +30,41 @@ impl PaymentProcessor {
         Self {
             methods: Arc::new(RwLock::new(HashMap::new())),
             payments: Arc::new(RwLock::new(HashMap::new())),
-            dunning: Arc::new(RwLock::new(HashMap::new())),
+            dunning: Arc::new(DunningEngine::default()),
+            stripe_keys: StripeKeys::default(),
+            sandbox: Arc::new(SandboxRegistry::new()),
+        }
+    }
+
+    /// Set Stripe live/test secret keys
+    pub fn with_stripe_keys(mut self, keys: StripeKeys) -> Self {
+        self.stripe_keys = keys;
+        self
+    }
+
+    /// Use shared sandbox registry
+    pub fn with_sandbox(mut self, sandbox: Arc<SandboxRegistry>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Set dunning policy (retry schedule, grace period, final action)
+    pub fn with_dunning(mut self, config: DunningConfig) -> Self {
+        self.dunning = Arc::new(DunningEngine::new(config));
+        self
+    }
+
+    /// Dunning engine (notification hooks, event subscription)
+    pub fn dunning(&self) -> Arc<DunningEngine> {
+        self.dunning.clone()
+    }
+
+    /// Stripe mode for a tenant: test mode for sandbox tenants
+    pub fn stripe_mode(&self, tenant_id: Uuid) -> StripeMode {
+        if self.sandbox.is_sandbox(tenant_id) {
+            StripeMode::Test
+        } else {
+            StripeMode::Live
         }
     }
 
@@ -64,27 +105,44 @@ impl PaymentProcessor {
         let method = self.get_default_method(tenant_id)
             .ok_or(PaymentError::NoPaymentMethod)?;
 
-        // In production: call Stripe API
+        // Sandbox tenants must never reach live mode
+        let mode = self.stripe_mode(tenant_id);
+        if mode == StripeMode::Test && self.stripe_keys.key_for(mode).is_none() {
+            return Err(PaymentError::StripeError("no test-mode key configured for sandbox tenant".into()));
+        }
+
+        let now = self.sandbox.now(tenant_id);
+        let result = self.charge(&method, amount, mode, now).await;
+
         let payment = Payment {
             id: Uuid::new_v4(),
             tenant_id,
             invoice_id,
             amount,
             currency: "USD".into(),
-            status: PaymentStatus::Succeeded, // Simulated
+            status: if result.is_ok() { PaymentStatus::Succeeded } else { PaymentStatus::Failed },
             payment_method_id: method.id,
-            stripe_payment_intent_id: Some(format!("pi_{}", Uuid::new_v4().to_string().replace("-", ""))),
-            created_at: Utc::now(),
-            error: None,
+            stripe_payment_intent_id: result.as_ref().ok().cloned(),
+            created_at: now,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            livemode: mode == StripeMode::Live,
         };
 
         self.payments.write().insert(payment.id, payment.clone());
-        self.clear_dunning(tenant_id);
 
-        Ok(payment)
+        match result {
+            Ok(_) => {
+                self.dunning.record_success(tenant_id, now);
+                Ok(payment)
+            }
+            Err(e) => {
+                self.handle_failure(tenant_id, invoice_id, amount, &e.to_string());
+                Err(e)
+            }
+        }
     }
 
-    /// Retry failed payment
+    /// Retry failed payment with the tenant's current default method
     pub async fn retry_payment(&self, payment_id: Uuid) -> Result<Payment, PaymentError> {
         let payment = self.payments.read()
             .get(&payment_id)
@@ -95,50 +153,87 @@ impl PaymentProcessor {
             return Err(PaymentError::InvalidState);
         }
 
-        // In production: retry with Stripe
+        let method = self.get_default_method(payment.tenant_id)
+            .ok_or(PaymentError::NoPaymentMethod)?;
+        let mode = self.stripe_mode(payment.tenant_id);
+        let now = self.sandbox.now(payment.tenant_id);
+        let result = self.charge(&method, payment.amount, mode, now).await;
+
         let mut updated = payment;
-        updated.status = PaymentStatus::Succeeded;
-        self.payments.write().insert(updated.id, updated.clone());
+        updated.payment_method_id = method.id;
+        match result {
+            Ok(intent) => {
+                updated.status = PaymentStatus::Succeeded;
+                updated.stripe_payment_intent_id = Some(intent);
+                updated.error = None;
+                self.payments.write().insert(updated.id, updated.clone());
+                self.dunning.record_success(updated.tenant_id, now);
+                Ok(updated)
+            }
+            Err(e) => {
+                updated.error = Some(e.to_string());
+                self.payments.write().insert(updated.id, updated.clone());
+                self.handle_failure(updated.tenant_id, updated.invoice_id, updated.amount, &e.to_string());
+                Err(e)
+            }
+        }
+    }
 
-        Ok(updated)
+    /// Handle failed payment (dunning): schedule a retry and notify, or
+    /// suspend/cancel once retries or the grace period are exhausted
+    pub fn handle_failure(&self, tenant_id: Uuid, invoice_id: Uuid, amount: Decimal, reason: &str) -> Vec<DunningEvent> {
+        let now = self.sandbox.now(tenant_id);
+        tracing::info!("Payment for invoice {} failed: {}", invoice_id, reason);
+        self.dunning.record_failure(tenant_id, invoice_id, amount, reason, now)
     }
 
-    /// Handle failed payment (dunning)
-    pub fn handle_failure(&self, tenant_id: Uuid, invoice_id: Uuid) {
-        let mut dunning = self.dunning.write();
-        let state = dunning.entry(tenant_id).or_insert_with(|| DunningState {
-            tenant_id,
-            invoice_id,
-            attempts: 0,
-            next_attempt: Utc::now() + chrono::Duration::days(1),
-            emails_sent: vec![],
-            status: DunningStatus::Active,
-        });
-
-        state.attempts += 1;
-        state.next_attempt = Utc::now() + chrono::Duration::days(2_i64.pow(state.attempts.min(5)));
-
-        // Determine email to send
-        let email_type = match state.attempts {
-            1 => DunningEmailType::FirstReminder,
-            2 => DunningEmailType::SecondReminder,
-            3 => DunningEmailType::FinalWarning,
-            _ => DunningEmailType::AccountSuspension,
-        };
+    /// Run due dunning retries and expire lapsed grace periods
+    ///
+    /// Each tenant is evaluated on its own clock, so sandbox tenants only
+    /// progress when their clock is advanced. Returns tenants still in dunning.
+    pub async fn run_dunning(&self) -> Vec<DunningState> {
+        for state in self.dunning.active() {
+            let now = self.sandbox.now(state.tenant_id);
+            if state.grace_until <= now {
+                self.dunning.expire_grace(state.tenant_id, now);
+                continue;
+            }
+            if state.next_attempt.is_none_or(|t| t > now) {
+                continue;
+            }
+
+            let failed = self.payments.read()
+                .values()
+                .filter(|p| p.invoice_id == state.invoice_id && p.status == PaymentStatus::Failed)
+                .max_by_key(|p| p.created_at)
+                .map(|p| p.id);
+
+            let result = match failed {
+                Some(payment_id) => self.retry_payment(payment_id).await.map(|_| ()),
+                None => self.process_payment(state.tenant_id, state.invoice_id, state.amount).await.map(|_| ()),
+            };
+            if let Err(e) = result {
+                tracing::debug!("Dunning retry for tenant {} failed: {}", state.tenant_id, e);
+            }
+        }
 
-        state.emails_sent.push(DunningEmail {
-            email_type,
-            sent_at: Utc::now(),
-        });
+        self.dunning.active()
+    }
 
-        if state.attempts >= 4 {
-            state.status = DunningStatus::Exhausted;
-            // Trigger account suspension
-        }
+    /// Current dunning state for a tenant
+    pub fn dunning_state(&self, tenant_id: Uuid) -> Option<DunningState> {
+        self.dunning.get(tenant_id)
     }
 
-    fn clear_dunning(&self, tenant_id: Uuid) {
-        self.dunning.write().remove(&tenant_id);
+    /// Charge a payment method
+    async fn charge(&self, method: &PaymentMethod, amount: Decimal, mode: StripeMode, now: DateTime<Utc>) -> Result<String, PaymentError> {
+        // In production: create and confirm a PaymentIntent with
+        // stripe_keys.key_for(mode); decline codes map to PaymentError::Declined
+        let _ = (amount, mode);
+        if matches!(method.method_type, PaymentMethodType::Card) && method.is_expired(now) {
+            return Err(PaymentError::Declined("expired_card".into()));
+        }
+        Ok(format!("pi_{}", Uuid::new_v4().to_string().replace("-", "")))
     }
 
     /// Get payment history
@@ -170,6 +265,14 @@ pub struct PaymentMethod {
     pub created_at: DateTime<Utc>,
 }
 
+impl PaymentMethod {
+    /// Card expired before `now` (valid through the end of its expiry month)
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        let (year, month) = (now.year(), now.month());
+        (self.exp_year as i32, self.exp_month as u32) < (year, month)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum PaymentMethodType {
     Card,
@@ -190,6 +293,40 @@ pub struct Payment {
     pub stripe_payment_intent_id: Option<String>,
     pub created_at: DateTime<Utc>,
     pub error: Option<String>,
+    /// Charged through Stripe live mode (false for sandbox tenants)
+    #[serde(default = "default_livemode")]
+    pub livemode: bool,
+}
+
+fn default_livemode() -> bool { true }
+
+/// Stripe API mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StripeMode {
+    /// Real charges
+    Live,
+    /// Test mode (sandbox tenants)
+    Test,
+}
+
+/// Stripe secret keys per mode
+#[derive(Debug, Clone, Default)]
+pub struct StripeKeys {
+    /// `sk_live_...`
+    pub live_secret_key: Option<String>,
+    /// `sk_test_...`
+    pub test_secret_key: Option<String>,
+}
+
+impl StripeKeys {
+    /// Key for a mode; a key whose prefix does not match the mode is ignored
+    pub fn key_for(&self, mode: StripeMode) -> Option<&str> {
+        let (key, prefix) = match mode {
+            StripeMode::Live => (&self.live_secret_key, "sk_live_"),
+            StripeMode::Test => (&self.test_secret_key, "sk_test_"),
+        };
+        key.as_deref().filter(|k| k.starts_with(prefix))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -223,34 +360,119 @@ impl std::fmt::Display for PaymentError {
     }
 }
 
-/// Dunning state
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DunningState {
-    pub tenant_id: Uuid,
-    pub invoice_id: Uuid,
-    pub attempts: u32,
-    pub next_attempt: DateTime<Utc>,
-    pub emails_sent: Vec<DunningEmail>,
-    pub status: DunningStatus,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dunning::DunningStatus;
+    use crate::subscriptions::{BillingPeriod, SubscriptionManager, SubscriptionStatus};
+    use chrono::{Duration, TimeZone};
+    use rust_decimal_macros::dec;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum DunningStatus {
-    Active,
-    Exhausted,
-    Resolved,
-}
+    fn card(tenant_id: Uuid, exp_year: u16) -> PaymentMethod {
+        PaymentMethod {
+            id: Uuid::new_v4(),
+            tenant_id,
+            method_type: PaymentMethodType::Card,
+            is_default: true,
+            last_four: "4242".into(),
+            exp_month: 12,
+            exp_year,
+            brand: Some("visa".into()),
+            stripe_payment_method_id: format!("pm_{}", exp_year),
+            created_at: Utc::now(),
+        }
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DunningEmail {
-    pub email_type: DunningEmailType,
-    pub sent_at: DateTime<Utc>,
-}
+    /// Processor and subscriptions for sandbox tenants driven by dunning
+    fn billing() -> (PaymentProcessor, Arc<SubscriptionManager>, Arc<SandboxRegistry>) {
+        let sandbox = Arc::new(SandboxRegistry::new());
+        let keys = StripeKeys { live_secret_key: None, test_secret_key: Some("sk_test_123".into()) };
+        let payments = PaymentProcessor::new().with_stripe_keys(keys).with_sandbox(sandbox.clone());
+        let subscriptions = Arc::new(SubscriptionManager::new().with_sandbox(sandbox.clone()));
+        payments.dunning().add_notifier(subscriptions.clone());
+        (payments, subscriptions, sandbox)
+    }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum DunningEmailType {
-    FirstReminder,
-    SecondReminder,
-    FinalWarning,
-    AccountSuspension,
+    #[test]
+    fn test_card_expiry() {
+        let mut method = card(Uuid::new_v4(), 2026);
+        method.exp_month = 10;
+        assert!(!method.is_expired(Utc.with_ymd_and_hms(2026, 10, 31, 23, 59, 0).unwrap()));
+        assert!(method.is_expired(Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap()));
+        assert!(method.is_expired(Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_stripe_keys_match_mode() {
+        let keys = StripeKeys { live_secret_key: Some("sk_test_wrong".into()), test_secret_key: Some("sk_test_ok".into()) };
+        assert_eq!(keys.key_for(StripeMode::Live), None);
+        assert_eq!(keys.key_for(StripeMode::Test), Some("sk_test_ok"));
+    }
+
+    #[tokio::test]
+    async fn test_declined_charge_retried_until_recovered() {
+        let (payments, subscriptions, sandbox) = billing();
+        let tenant_id = Uuid::new_v4();
+        sandbox.enroll(tenant_id);
+        let sub = subscriptions.create(tenant_id, "pro", BillingPeriod::Monthly);
+        payments.add_payment_method(tenant_id, card(tenant_id, 2020));
+        let invoice_id = Uuid::new_v4();
+
+        assert!(matches!(payments.process_payment(tenant_id, invoice_id, dec!(99)).await, Err(PaymentError::Declined(_))));
+        let state = payments.dunning_state(tenant_id).unwrap();
+        assert_eq!(state.attempts, 1);
+        assert_eq!(subscriptions.get(sub.id).unwrap().status, SubscriptionStatus::PastDue);
+        assert_eq!(subscriptions.get(sub.id).unwrap().grace_period_end, Some(state.grace_until));
+
+        // Not due yet, then due and declined again
+        assert_eq!(payments.run_dunning().await[0].attempts, 1);
+        sandbox.advance(tenant_id, Duration::days(1)).unwrap();
+        assert_eq!(payments.run_dunning().await[0].attempts, 2);
+
+        // The customer updates their card; the next retry uses it
+        payments.methods.write().get_mut(&tenant_id).unwrap()[0].is_default = false;
+        let valid = payments.add_payment_method(tenant_id, card(tenant_id, 2099));
+        sandbox.advance(tenant_id, Duration::days(3)).unwrap();
+        assert!(payments.run_dunning().await.is_empty());
+
+        assert_eq!(payments.dunning_state(tenant_id).unwrap().status, DunningStatus::Resolved);
+        let history = payments.get_payments(tenant_id);
+        assert_eq!(history.len(), 1);
+        assert_eq!((history[0].status, history[0].payment_method_id), (PaymentStatus::Succeeded, valid));
+        assert!(history[0].stripe_payment_intent_id.is_some() && history[0].error.is_none());
+        assert!(!history[0].livemode);
+        let sub = subscriptions.get(sub.id).unwrap();
+        assert_eq!((sub.status, sub.grace_period_end), (SubscriptionStatus::Active, None));
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_grace_period_suspends() {
+        let (payments, subscriptions, sandbox) = billing();
+        let tenant_id = Uuid::new_v4();
+        sandbox.enroll(tenant_id);
+        let sub = subscriptions.create(tenant_id, "pro", BillingPeriod::Monthly);
+        payments.add_payment_method(tenant_id, card(tenant_id, 2020));
+
+        assert!(payments.process_payment(tenant_id, Uuid::new_v4(), dec!(99)).await.is_err());
+        sandbox.advance(tenant_id, Duration::days(14)).unwrap();
+        assert!(payments.run_dunning().await.is_empty());
+
+        let state = payments.dunning_state(tenant_id).unwrap();
+        assert_eq!((state.status, state.attempts), (DunningStatus::Exhausted, 1));
+        assert_eq!(subscriptions.get(sub.id).unwrap().status, SubscriptionStatus::Suspended);
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_without_test_key_never_charges() {
+        let sandbox = Arc::new(SandboxRegistry::new());
+        let payments = PaymentProcessor::new().with_sandbox(sandbox.clone());
+        let tenant_id = Uuid::new_v4();
+        sandbox.enroll(tenant_id);
+
+        assert!(matches!(payments.process_payment(tenant_id, Uuid::new_v4(), dec!(1)).await, Err(PaymentError::NoPaymentMethod)));
+        payments.add_payment_method(tenant_id, card(tenant_id, 2099));
+        assert!(matches!(payments.process_payment(tenant_id, Uuid::new_v4(), dec!(1)).await, Err(PaymentError::StripeError(_))));
+        assert!(payments.get_payments(tenant_id).is_empty());
+        assert!(payments.dunning_state(tenant_id).is_none());
+    }
 }
@@ -108,6 +108,14 @@ impl PricingEngine {
         self.plans.read().values().cloned().collect()
     }
 
+    /// Base price of a plan for a tenant (custom pricing applied)
+    pub fn base_price(&self, tenant_id: Uuid, plan_id: &str) -> Option<Decimal> {
+        let plan = self.get_plan(plan_id)?;
+        Some(self.custom.read().get(&tenant_id)
+            .and_then(|c| c.custom_base_price)
+            .unwrap_or(plan.base_price))
+    }
+
     /// Calculate invoice amount
     pub fn calculate(&self, tenant_id: Uuid, plan_id: &str, usage: &MonthlyUsage) -> PricingResult {
         let plan = match self.get_plan(plan_id) {
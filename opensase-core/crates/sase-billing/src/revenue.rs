@@ -0,0 +1,954 @@
+//! Revenue Recognition (ASC 606)
+//!
+//! Splits each invoice into performance obligations with a recognition
+//! schedule. Subscription and proration charges are satisfied over time:
+//! they are deferred when billed and recognized ratably by day over the
+//! service period, one entry per calendar month. Usage overages are
+//! satisfied as consumed and recognized when billed. Discounts are
+//! allocated across the obligations in proportion to their price; tax is a
+//! liability, not revenue, and sandbox invoices are never scheduled.
+//!
+//! Mid-term modifications (early termination, extension, price changes
+//! outside an invoice) are accounted for prospectively: the unrecognized
+//! balance plus any change in consideration is spread over the remaining
+//! term from the effective date.
+//!
+//! Every movement is posted to a double-entry journal, exported as CSV or
+//! JSON for the general ledger.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use uuid::Uuid;
+use chrono::{Datelike, Months, NaiveDate, Utc};
+
+use crate::invoicing::{Invoice, ItemType};
+
+/// Revenue recognizer
+pub struct RevenueRecognizer {
+    schedules: Arc<RwLock<HashMap<Uuid, RecognitionSchedule>>>,
+    journal: Arc<RwLock<Vec<JournalEntry>>>,
+    accounts: AccountCodes,
+}
+
+/// General ledger accounts journal entries are posted to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountCodes {
+    /// Accounts receivable
+    pub receivable: String,
+    /// Deferred revenue (contract liability)
+    pub deferred_revenue: String,
+    /// Subscription revenue
+    pub subscription_revenue: String,
+    /// Usage revenue
+    pub usage_revenue: String,
+}
+
+impl Default for AccountCodes {
+    fn default() -> Self {
+        Self {
+            receivable: "1200".into(),
+            deferred_revenue: "2400".into(),
+            subscription_revenue: "4000".into(),
+            usage_revenue: "4100".into(),
+        }
+    }
+}
+
+impl RevenueRecognizer {
+    /// Create recognizer with the default chart of accounts
+    pub fn new() -> Self {
+        Self {
+            schedules: Arc::new(RwLock::new(HashMap::new())),
+            journal: Arc::new(RwLock::new(Vec::new())),
+            accounts: AccountCodes::default(),
+        }
+    }
+
+    /// Post to these accounts
+    pub fn with_accounts(mut self, accounts: AccountCodes) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    /// Create the recognition schedules for an invoice and post its billing
+    /// entries. Scheduling the same invoice again returns the existing
+    /// schedules.
+    pub fn schedule_invoice(&self, invoice: &Invoice) -> Vec<RecognitionSchedule> {
+        if invoice.sandbox {
+            return Vec::new();
+        }
+
+        let mut schedules = self.schedules.write();
+        let existing: Vec<_> = schedules.values()
+            .filter(|s| s.invoice_id == invoice.id)
+            .cloned()
+            .collect();
+        if !existing.is_empty() {
+            return existing;
+        }
+
+        let billed = invoice.created_at.date_naive();
+        let mut created = Vec::new();
+        let mut journal = Vec::new();
+
+        for (description, obligation, amount) in allocate(invoice) {
+            let id = Uuid::new_v4();
+            let (entries, credit_account) = match obligation {
+                Obligation::OverTime => (
+                    ratable_entries(amount, invoice.period_start, invoice.period_end),
+                    &self.accounts.deferred_revenue,
+                ),
+                Obligation::PointInTime => (
+                    vec![ScheduleEntry {
+                        period: month_start(invoice.period_start),
+                        amount,
+                        recognized_on: Some(billed),
+                    }],
+                    &self.accounts.usage_revenue,
+                ),
+            };
+
+            journal.push(JournalEntry {
+                id: Uuid::new_v4(),
+                date: billed,
+                kind: JournalKind::Billing,
+                tenant_id: invoice.tenant_id,
+                invoice_number: invoice.invoice_number.clone(),
+                schedule_id: Some(id),
+                debit_account: self.accounts.receivable.clone(),
+                credit_account: credit_account.clone(),
+                amount,
+                currency: invoice.currency.clone(),
+                memo: description.clone(),
+            });
+
+            let schedule = RecognitionSchedule {
+                id,
+                tenant_id: invoice.tenant_id,
+                subscription_id: invoice.subscription_id,
+                invoice_id: invoice.id,
+                invoice_number: invoice.invoice_number.clone(),
+                description,
+                obligation,
+                currency: invoice.currency.clone(),
+                total: amount,
+                service_start: invoice.period_start,
+                service_end: invoice.period_end,
+                segment_start: invoice.period_start,
+                status: if obligation == Obligation::PointInTime {
+                    ScheduleStatus::Completed
+                } else {
+                    ScheduleStatus::Open
+                },
+                entries,
+                created_at: Utc::now(),
+            };
+            schedules.insert(id, schedule.clone());
+            created.push(schedule);
+        }
+
+        self.journal.write().extend(journal);
+        created
+    }
+
+    /// Recognize every scheduled month that ended on or before `through`
+    /// (a month-end close), returning the entries posted
+    pub fn recognize_through(&self, through: NaiveDate) -> Vec<JournalEntry> {
+        let mut posted = Vec::new();
+        let mut schedules = self.schedules.write();
+
+        for schedule in schedules.values_mut().filter(|s| s.status == ScheduleStatus::Open) {
+            for entry in schedule.entries.iter_mut().filter(|e| e.recognized_on.is_none()) {
+                let month_end = next_month(entry.period).pred_opt().unwrap_or(entry.period);
+                if month_end > through {
+                    continue;
+                }
+                entry.recognized_on = Some(month_end);
+                if entry.amount == dec!(0) {
+                    continue;
+                }
+                posted.push(JournalEntry {
+                    id: Uuid::new_v4(),
+                    date: month_end,
+                    kind: JournalKind::Recognition,
+                    tenant_id: schedule.tenant_id,
+                    invoice_number: schedule.invoice_number.clone(),
+                    schedule_id: Some(schedule.id),
+                    debit_account: self.accounts.deferred_revenue.clone(),
+                    credit_account: self.accounts.subscription_revenue.clone(),
+                    amount: entry.amount,
+                    currency: schedule.currency.clone(),
+                    memo: format!("{} ({})", schedule.description, entry.period.format("%Y-%m")),
+                });
+            }
+            if schedule.entries.iter().all(|e| e.recognized_on.is_some()) {
+                schedule.status = ScheduleStatus::Completed;
+            }
+        }
+
+        self.journal.write().extend(posted.iter().cloned());
+        posted
+    }
+
+    /// Apply a mid-term contract modification to a subscription's open
+    /// over-time schedules, prospectively from the effective date
+    ///
+    /// The change in consideration is allocated across the schedules in
+    /// proportion to their unrecognized balance. Months already closed
+    /// cannot be modified.
+    pub fn modify(&self, modification: &Modification) -> Result<Vec<RecognitionSchedule>, RevenueError> {
+        let effective = modification.effective;
+        let mut schedules = self.schedules.write();
+
+        let mut affected: Vec<&mut RecognitionSchedule> = schedules.values_mut()
+            .filter(|s| {
+                s.subscription_id == modification.subscription_id
+                    && s.obligation == Obligation::OverTime
+                    && s.status == ScheduleStatus::Open
+                    && s.service_end > effective
+            })
+            .collect();
+        if affected.is_empty() {
+            return Err(RevenueError::NoOpenSchedules);
+        }
+        affected.sort_by_key(|s| s.created_at);
+
+        // Validate every schedule before changing any
+        let mut splits = Vec::with_capacity(affected.len());
+        for schedule in &affected {
+            let new_end = modification.new_end.unwrap_or(schedule.service_end);
+            if new_end < effective {
+                return Err(RevenueError::InvalidModification("new end precedes the effective date".into()));
+            }
+            splits.push(split_at(schedule, effective)?);
+        }
+
+        let pool: Decimal = splits.iter().map(|(_, unrecognized)| *unrecognized).sum();
+        let mut shares = Vec::with_capacity(splits.len());
+        let mut allocated = dec!(0);
+        for (index, (_, unrecognized)) in splits.iter().enumerate() {
+            let share = if index + 1 == splits.len() {
+                modification.consideration_change - allocated
+            } else if pool > dec!(0) {
+                (modification.consideration_change * *unrecognized / pool).round_dp(2)
+            } else {
+                dec!(0)
+            };
+            allocated += share;
+            if *unrecognized + share < dec!(0) {
+                return Err(RevenueError::ExceedsDeferred);
+            }
+            shares.push(share);
+        }
+
+        let mut journal = Vec::new();
+        let mut modified = Vec::with_capacity(affected.len());
+        for ((schedule, (kept, unrecognized)), share) in affected.into_iter().zip(splits).zip(shares) {
+            let new_end = modification.new_end.unwrap_or(schedule.service_end);
+            let mut entries = kept;
+            for entry in ratable_entries(unrecognized + share, effective, new_end) {
+                match entries.last_mut() {
+                    Some(last) if last.period == entry.period && last.recognized_on.is_none() => last.amount += entry.amount,
+                    _ => entries.push(entry),
+                }
+            }
+
+            schedule.entries = entries;
+            schedule.segment_start = effective;
+            schedule.service_end = new_end;
+            schedule.total += share;
+
+            if share != dec!(0) {
+                let (debit_account, credit_account) = if share > dec!(0) {
+                    (self.accounts.receivable.clone(), self.accounts.deferred_revenue.clone())
+                } else {
+                    (self.accounts.deferred_revenue.clone(), self.accounts.receivable.clone())
+                };
+                journal.push(JournalEntry {
+                    id: Uuid::new_v4(),
+                    date: effective,
+                    kind: JournalKind::Modification,
+                    tenant_id: schedule.tenant_id,
+                    invoice_number: schedule.invoice_number.clone(),
+                    schedule_id: Some(schedule.id),
+                    debit_account,
+                    credit_account,
+                    amount: share.abs(),
+                    currency: schedule.currency.clone(),
+                    memo: modification.memo.clone(),
+                });
+            }
+            modified.push(schedule.clone());
+        }
+
+        self.journal.write().extend(journal);
+        Ok(modified)
+    }
+
+    /// Get schedule
+    pub fn get(&self, id: Uuid) -> Option<RecognitionSchedule> {
+        self.schedules.read().get(&id).cloned()
+    }
+
+    /// Get schedules for tenant
+    pub fn get_for_tenant(&self, tenant_id: Uuid) -> Vec<RecognitionSchedule> {
+        let mut schedules: Vec<_> = self.schedules.read()
+            .values()
+            .filter(|s| s.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        schedules.sort_by_key(|s| s.created_at);
+        schedules
+    }
+
+    /// Deferred revenue balance: billed but not recognized as of `as_of`
+    pub fn deferred_balance(&self, as_of: NaiveDate) -> Decimal {
+        self.schedules.read()
+            .values()
+            .filter(|s| s.obligation == Obligation::OverTime)
+            .flat_map(|s| &s.entries)
+            .filter(|e| e.recognized_on.map(|d| d > as_of).unwrap_or(true))
+            .map(|e| e.amount)
+            .sum()
+    }
+
+    /// Journal entries dated within `[from, to]`, in posting order
+    pub fn journal(&self, from: NaiveDate, to: NaiveDate) -> Vec<JournalEntry> {
+        self.journal.read()
+            .iter()
+            .filter(|e| e.date >= from && e.date <= to)
+            .cloned()
+            .collect()
+    }
+
+    /// Export journal entries dated within `[from, to]` for an accounting
+    /// system
+    pub fn export_journal(&self, from: NaiveDate, to: NaiveDate, format: JournalFormat) -> Vec<u8> {
+        let entries = self.journal(from, to);
+        match format {
+            JournalFormat::Csv => journal_csv(&entries).into_bytes(),
+            JournalFormat::Json => serde_json::to_vec_pretty(&entries).unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for RevenueRecognizer {
+    fn default() -> Self { Self::new() }
+}
+
+/// Allocate an invoice's transaction price to its charge lines
+///
+/// Discounts, proration credits and the committed-use discount reduce the
+/// charges in proportion to their amount; the last line takes the rounding
+/// remainder.
+fn allocate(invoice: &Invoice) -> Vec<(String, Obligation, Decimal)> {
+    let charges: Vec<_> = invoice.line_items.iter()
+        .filter(|i| i.amount > dec!(0))
+        .collect();
+    let gross: Decimal = charges.iter().map(|i| i.amount).sum();
+    if gross <= dec!(0) {
+        return Vec::new();
+    }
+
+    let promo: Decimal = invoice.line_items.iter()
+        .filter(|i| matches!(i.item_type, ItemType::Discount))
+        .map(|i| -i.amount)
+        .sum();
+    let negative: Decimal = invoice.line_items.iter()
+        .filter(|i| i.amount < dec!(0))
+        .map(|i| -i.amount)
+        .sum();
+    // Committed-use discount is on the invoice but not a line
+    let committed = (invoice.discount - promo).max(dec!(0));
+    let price = (gross - negative - committed).max(dec!(0));
+
+    let mut allocated = dec!(0);
+    charges.iter().enumerate()
+        .map(|(index, item)| {
+            let amount = if index + 1 == charges.len() {
+                price - allocated
+            } else {
+                (price * item.amount / gross).round_dp(2)
+            };
+            allocated += amount;
+            let obligation = match item.item_type {
+                ItemType::Usage | ItemType::Adjustment => Obligation::PointInTime,
+                _ => Obligation::OverTime,
+            };
+            (item.description.clone(), obligation, amount)
+        })
+        .filter(|(_, _, amount)| *amount > dec!(0))
+        .collect()
+}
+
+/// Entries kept as they are and the unrecognized balance from
+/// `effective` on
+fn split_at(schedule: &RecognitionSchedule, effective: NaiveDate) -> Result<(Vec<ScheduleEntry>, Decimal), RevenueError> {
+    if effective < schedule.segment_start {
+        return Err(RevenueError::InvalidModification("effective date precedes the schedule".into()));
+    }
+    let effective_month = month_start(effective);
+    if schedule.entries.iter().any(|e| e.period >= effective_month && e.recognized_on.is_some()) {
+        return Err(RevenueError::PeriodClosed(effective_month));
+    }
+
+    let mut kept = Vec::new();
+    let mut unrecognized = dec!(0);
+    for entry in &schedule.entries {
+        if entry.recognized_on.is_some() || entry.period < effective_month {
+            kept.push(entry.clone());
+        } else if entry.period == effective_month {
+            // Days of the month before the effective date stay as scheduled
+            let from = schedule.segment_start.max(effective_month);
+            let to = schedule.service_end.min(next_month(effective_month));
+            let days = (to - from).num_days();
+            let before = (effective - from).num_days().max(0);
+            let keep = if days > 0 {
+                (entry.amount * Decimal::from(before) / Decimal::from(days)).round_dp(2)
+            } else {
+                dec!(0)
+            };
+            unrecognized += entry.amount - keep;
+            if keep != dec!(0) {
+                kept.push(ScheduleEntry { amount: keep, ..entry.clone() });
+            }
+        } else {
+            unrecognized += entry.amount;
+        }
+    }
+    Ok((kept, unrecognized))
+}
+
+/// Split `amount` over `[start, end)` by calendar month in proportion to
+/// days; the last month takes the rounding remainder. An empty span puts
+/// the whole amount in `start`'s month.
+fn ratable_entries(amount: Decimal, start: NaiveDate, end: NaiveDate) -> Vec<ScheduleEntry> {
+    if end <= start {
+        return vec![ScheduleEntry { period: month_start(start), amount, recognized_on: None }];
+    }
+
+    let total_days = Decimal::from((end - start).num_days());
+    let mut entries = Vec::new();
+    let mut allocated = dec!(0);
+    let mut cursor = start;
+    while cursor < end {
+        let until = next_month(cursor).min(end);
+        let share = if until == end {
+            amount - allocated
+        } else {
+            (amount * Decimal::from((until - cursor).num_days()) / total_days).round_dp(2)
+        };
+        allocated += share;
+        entries.push(ScheduleEntry { period: month_start(cursor), amount: share, recognized_on: None });
+        cursor = until;
+    }
+    entries
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn next_month(date: NaiveDate) -> NaiveDate {
+    let start = month_start(date);
+    start.checked_add_months(Months::new(1)).unwrap_or(start)
+}
+
+fn journal_csv(entries: &[JournalEntry]) -> String {
+    let mut out = String::from(
+        "date,entry_id,kind,tenant_id,invoice_number,schedule_id,debit_account,credit_account,amount,currency,memo\r\n",
+    );
+    for entry in entries {
+        let fields = [
+            entry.date.to_string(),
+            entry.id.to_string(),
+            entry.kind.as_str().to_string(),
+            entry.tenant_id.to_string(),
+            entry.invoice_number.clone(),
+            entry.schedule_id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.debit_account.clone(),
+            entry.credit_account.clone(),
+            entry.amount.to_string(),
+            entry.currency.clone(),
+            entry.memo.clone(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Revenue recognition schedule for one performance obligation on an
+/// invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecognitionSchedule {
+    /// Schedule ID
+    pub id: Uuid,
+    /// Tenant
+    pub tenant_id: Uuid,
+    /// Subscription billed
+    pub subscription_id: Uuid,
+    /// Invoice the amount was billed on
+    pub invoice_id: Uuid,
+    /// Invoice number
+    pub invoice_number: String,
+    /// Invoice line description
+    pub description: String,
+    /// How the obligation is satisfied
+    pub obligation: Obligation,
+    /// Currency
+    pub currency: String,
+    /// Transaction price allocated, including modifications
+    pub total: Decimal,
+    /// First day of service
+    pub service_start: NaiveDate,
+    /// Day after the last day of service
+    pub service_end: NaiveDate,
+    /// Start of the span the unrecognized balance is spread over; moves to
+    /// the effective date of a modification
+    pub segment_start: NaiveDate,
+    /// Monthly entries, oldest first
+    pub entries: Vec<ScheduleEntry>,
+    /// Status
+    pub status: ScheduleStatus,
+    /// When the schedule was created
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+impl RecognitionSchedule {
+    /// Amount recognized so far
+    pub fn recognized(&self) -> Decimal {
+        self.entries.iter().filter(|e| e.recognized_on.is_some()).map(|e| e.amount).sum()
+    }
+
+    /// Amount still deferred
+    pub fn deferred(&self) -> Decimal {
+        self.entries.iter().filter(|e| e.recognized_on.is_none()).map(|e| e.amount).sum()
+    }
+}
+
+/// Revenue to recognize in one month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    /// First day of the month
+    pub period: NaiveDate,
+    /// Amount
+    pub amount: Decimal,
+    /// Date recognized (month end, or billing date for point-in-time)
+    pub recognized_on: Option<NaiveDate>,
+}
+
+/// How a performance obligation is satisfied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Obligation {
+    /// Ratably over the service period (subscriptions, prorations)
+    OverTime,
+    /// When billed (usage already consumed)
+    PointInTime,
+}
+
+/// Schedule status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleStatus {
+    /// Revenue still deferred
+    Open,
+    /// Fully recognized
+    Completed,
+}
+
+/// Mid-term contract modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Modification {
+    /// Subscription modified
+    pub subscription_id: Uuid,
+    /// Date the modification takes effect
+    pub effective: NaiveDate,
+    /// Change in consideration for the remaining term; negative for
+    /// refunds and credit memos
+    pub consideration_change: Decimal,
+    /// New end of service (day after the last day); the effective date for
+    /// immediate termination, None to keep the current term
+    pub new_end: Option<NaiveDate>,
+    /// Journal memo
+    pub memo: String,
+}
+
+/// General ledger journal entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Entry ID
+    pub id: Uuid,
+    /// Posting date
+    pub date: NaiveDate,
+    /// What posted the entry
+    pub kind: JournalKind,
+    /// Tenant
+    pub tenant_id: Uuid,
+    /// Invoice number
+    pub invoice_number: String,
+    /// Recognition schedule
+    pub schedule_id: Option<Uuid>,
+    /// Account debited
+    pub debit_account: String,
+    /// Account credited
+    pub credit_account: String,
+    /// Amount
+    pub amount: Decimal,
+    /// Currency
+    pub currency: String,
+    /// Description
+    pub memo: String,
+}
+
+/// Journal entry source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalKind {
+    /// Invoice billed
+    Billing,
+    /// Deferred revenue recognized at month end
+    Recognition,
+    /// Contract modification
+    Modification,
+}
+
+impl JournalKind {
+    /// Export name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Billing => "billing",
+            Self::Recognition => "recognition",
+            Self::Modification => "modification",
+        }
+    }
+}
+
+/// Journal export format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalFormat {
+    /// RFC 4180 CSV, one row per entry
+    Csv,
+    /// JSON array of entries
+    Json,
+}
+
+/// Revenue recognition error
+#[derive(Debug, Clone)]
+pub enum RevenueError {
+    /// No open over-time schedule covers the effective date
+    NoOpenSchedules,
+    /// The month has already been recognized
+    PeriodClosed(NaiveDate),
+    /// Refund exceeds the deferred balance
+    ExceedsDeferred,
+    /// Modification dates are inconsistent
+    InvalidModification(String),
+}
+
+impl std::fmt::Display for RevenueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoOpenSchedules => write!(f, "No open revenue schedules for subscription"),
+            Self::PeriodClosed(month) => write!(f, "Revenue for {} already recognized", month.format("%Y-%m")),
+            Self::ExceedsDeferred => write!(f, "Reduction exceeds deferred revenue"),
+            Self::InvalidModification(reason) => write!(f, "Invalid modification: {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoicing::{InvoiceLineItem, InvoiceStatus};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn item(description: &str, item_type: ItemType, amount: Decimal) -> InvoiceLineItem {
+        InvoiceLineItem {
+            description: description.into(),
+            quantity: 1.0,
+            unit_price: amount,
+            amount,
+            item_type,
+            tax_amount: dec!(0),
+        }
+    }
+
+    fn invoice(subscription_id: Uuid, start: NaiveDate, end: NaiveDate, line_items: Vec<InvoiceLineItem>) -> Invoice {
+        let promo: Decimal = line_items.iter()
+            .filter(|i| matches!(i.item_type, ItemType::Discount))
+            .map(|i| -i.amount)
+            .sum();
+        Invoice {
+            id: Uuid::new_v4(),
+            invoice_number: "INV-001001".into(),
+            tenant_id: Uuid::new_v4(),
+            subscription_id,
+            period_start: start,
+            period_end: end,
+            status: InvoiceStatus::Open,
+            subtotal: line_items.iter().map(|i| i.amount).sum(),
+            line_items,
+            discount: promo,
+            discount_details: Vec::new(),
+            credits_applied: dec!(0),
+            credit_details: Vec::new(),
+            tax_rate: dec!(0),
+            tax_amount: dec!(0),
+            total: dec!(0),
+            currency: "USD".into(),
+            due_date: end,
+            created_at: start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            paid_at: None,
+            sandbox: false,
+            carried_credit: dec!(0),
+            tax_breakdown: None,
+        }
+    }
+
+    /// 910 over January to March 2024: 310, 290, 310
+    fn quarter(recognizer: &RevenueRecognizer) -> (Uuid, RecognitionSchedule) {
+        let subscription_id = Uuid::new_v4();
+        let invoice = invoice(subscription_id, date(2024, 1, 1), date(2024, 4, 1), vec![
+            item("Pro Plan - Quarterly", ItemType::Subscription, dec!(910)),
+        ]);
+        let schedule = recognizer.schedule_invoice(&invoice).remove(0);
+        (subscription_id, schedule)
+    }
+
+    fn amounts(schedule: &RecognitionSchedule) -> Vec<(NaiveDate, Decimal)> {
+        schedule.entries.iter().map(|e| (e.period, e.amount)).collect()
+    }
+
+    fn modification(subscription_id: Uuid, effective: NaiveDate, change: Decimal, new_end: Option<NaiveDate>) -> Modification {
+        Modification {
+            subscription_id,
+            effective,
+            consideration_change: change,
+            new_end,
+            memo: "Contract change".into(),
+        }
+    }
+
+    #[test]
+    fn test_ratable_schedule_by_day() {
+        let recognizer = RevenueRecognizer::new();
+        let invoice = invoice(Uuid::new_v4(), date(2024, 1, 15), date(2024, 2, 14), vec![
+            item("Pro Plan - Monthly", ItemType::Subscription, dec!(300)),
+        ]);
+        let schedules = recognizer.schedule_invoice(&invoice);
+
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].obligation, Obligation::OverTime);
+        assert_eq!(amounts(&schedules[0]), vec![(date(2024, 1, 1), dec!(170.00)), (date(2024, 2, 1), dec!(130.00))]);
+        assert_eq!(schedules[0].deferred(), dec!(300));
+
+        let billing = recognizer.journal(date(2024, 1, 1), date(2024, 12, 31));
+        assert_eq!(billing.len(), 1);
+        assert_eq!(billing[0].kind, JournalKind::Billing);
+        assert_eq!((billing[0].debit_account.as_str(), billing[0].credit_account.as_str()), ("1200", "2400"));
+
+        // Scheduling again is a no-op
+        let again = recognizer.schedule_invoice(&invoice);
+        assert_eq!(again[0].id, schedules[0].id);
+        assert_eq!(recognizer.journal(date(2024, 1, 1), date(2024, 12, 31)).len(), 1);
+    }
+
+    #[test]
+    fn test_ratable_rounding_remainder_goes_to_last_month() {
+        let entries = ratable_entries(dec!(100), date(2024, 1, 1), date(2024, 4, 1));
+        let split: Vec<Decimal> = entries.iter().map(|e| e.amount).collect();
+        assert_eq!(split, vec![dec!(34.07), dec!(31.87), dec!(34.06)]);
+
+        let empty = ratable_entries(dec!(50), date(2024, 2, 15), date(2024, 2, 15));
+        assert_eq!(empty.len(), 1);
+        assert_eq!((empty[0].period, empty[0].amount), (date(2024, 2, 1), dec!(50)));
+    }
+
+    #[test]
+    fn test_discounts_allocated_by_price() {
+        let recognizer = RevenueRecognizer::new();
+        let mut invoice = invoice(Uuid::new_v4(), date(2024, 1, 1), date(2024, 1, 31), vec![
+            item("Pro Plan - Monthly", ItemType::Subscription, dec!(100)),
+            item("Bandwidth overage", ItemType::Usage, dec!(50)),
+            item("Promo WELCOME", ItemType::Discount, dec!(-30)),
+        ]);
+        // 15 committed-use discount on top of the 30 promo line
+        invoice.discount = dec!(45);
+        let schedules = recognizer.schedule_invoice(&invoice);
+
+        let allocated: Vec<(Obligation, Decimal)> = schedules.iter().map(|s| (s.obligation, s.total)).collect();
+        assert_eq!(allocated, vec![(Obligation::OverTime, dec!(70.00)), (Obligation::PointInTime, dec!(35.00))]);
+        assert_eq!(schedules[1].status, ScheduleStatus::Completed);
+        assert_eq!(schedules[1].entries[0].recognized_on, Some(date(2024, 1, 1)));
+        assert_eq!(recognizer.deferred_balance(date(2024, 1, 1)), dec!(70.00));
+
+        let journal = recognizer.journal(date(2024, 1, 1), date(2024, 1, 1));
+        assert_eq!(journal[1].credit_account, "4100");
+    }
+
+    #[test]
+    fn test_allocation_rounding_and_credits() {
+        let invoice = invoice(Uuid::new_v4(), date(2024, 1, 1), date(2024, 1, 31), vec![
+            item("A", ItemType::Subscription, dec!(100)),
+            item("B", ItemType::Subscription, dec!(100)),
+            item("C", ItemType::Proration, dec!(100)),
+            item("Plan change credit", ItemType::Proration, dec!(-200)),
+        ]);
+        let allocated: Vec<Decimal> = allocate(&invoice).into_iter().map(|(_, _, amount)| amount).collect();
+        assert_eq!(allocated, vec![dec!(33.33), dec!(33.33), dec!(33.34)]);
+
+        // Credits exceeding the charges leave nothing to recognize
+        let credit_only = self::invoice(Uuid::new_v4(), date(2024, 1, 1), date(2024, 1, 31), vec![
+            item("Pro Plan - Monthly", ItemType::Subscription, dec!(99)),
+            item("Plan change credit", ItemType::Proration, dec!(-150)),
+        ]);
+        assert!(allocate(&credit_only).is_empty());
+        assert!(RevenueRecognizer::new().schedule_invoice(&credit_only).is_empty());
+    }
+
+    #[test]
+    fn test_sandbox_invoices_are_not_scheduled() {
+        let recognizer = RevenueRecognizer::new();
+        let mut invoice = invoice(Uuid::new_v4(), date(2024, 1, 1), date(2024, 1, 31), vec![
+            item("Pro Plan - Monthly", ItemType::Subscription, dec!(99)),
+        ]);
+        invoice.sandbox = true;
+        assert!(recognizer.schedule_invoice(&invoice).is_empty());
+        assert!(recognizer.journal(date(2024, 1, 1), date(2024, 12, 31)).is_empty());
+    }
+
+    #[test]
+    fn test_recognize_at_month_end() {
+        let recognizer = RevenueRecognizer::new();
+        let (_, schedule) = quarter(&recognizer);
+
+        assert!(recognizer.recognize_through(date(2024, 1, 30)).is_empty());
+        let january = recognizer.recognize_through(date(2024, 1, 31));
+        assert_eq!(january.len(), 1);
+        assert_eq!((january[0].date, january[0].amount), (date(2024, 1, 31), dec!(310)));
+        assert_eq!((january[0].debit_account.as_str(), january[0].credit_account.as_str()), ("2400", "4000"));
+        assert_eq!(recognizer.deferred_balance(date(2024, 1, 31)), dec!(600));
+        // Balance as of a date before the close still includes January
+        assert_eq!(recognizer.deferred_balance(date(2024, 1, 30)), dec!(910));
+
+        assert!(recognizer.recognize_through(date(2024, 1, 31)).is_empty());
+        assert_eq!(recognizer.recognize_through(date(2024, 3, 31)).len(), 2);
+        let schedule = recognizer.get(schedule.id).unwrap();
+        assert_eq!(schedule.status, ScheduleStatus::Completed);
+        assert_eq!(schedule.recognized(), dec!(910));
+    }
+
+    #[test]
+    fn test_termination_refunds_the_unrecognized_balance() {
+        let recognizer = RevenueRecognizer::new();
+        let (subscription_id, schedule) = quarter(&recognizer);
+        recognizer.recognize_through(date(2024, 1, 31));
+
+        // 14 of February's 29 days are kept: 140 of 290
+        let too_much = modification(subscription_id, date(2024, 2, 15), dec!(-460.01), Some(date(2024, 2, 15)));
+        assert!(matches!(recognizer.modify(&too_much), Err(RevenueError::ExceedsDeferred)));
+        assert_eq!(recognizer.get(schedule.id).unwrap().total, dec!(910));
+
+        let terminate = modification(subscription_id, date(2024, 2, 15), dec!(-460), Some(date(2024, 2, 15)));
+        let modified = recognizer.modify(&terminate).unwrap().remove(0);
+        assert_eq!(amounts(&modified), vec![(date(2024, 1, 1), dec!(310)), (date(2024, 2, 1), dec!(140.00))]);
+        assert_eq!(modified.total, dec!(450));
+        assert_eq!(modified.service_end, date(2024, 2, 15));
+
+        let journal = recognizer.journal(date(2024, 2, 15), date(2024, 2, 15));
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal[0].kind, JournalKind::Modification);
+        assert_eq!((journal[0].debit_account.as_str(), journal[0].credit_account.as_str()), ("2400", "1200"));
+        assert_eq!(journal[0].amount, dec!(460));
+    }
+
+    #[test]
+    fn test_price_increase_is_prospective() {
+        let recognizer = RevenueRecognizer::new();
+        let (subscription_id, _) = quarter(&recognizer);
+
+        // 460 unrecognized plus 290 over the 46 days left
+        let upgrade = modification(subscription_id, date(2024, 2, 15), dec!(290), None);
+        let modified = recognizer.modify(&upgrade).unwrap().remove(0);
+        assert_eq!(amounts(&modified), vec![
+            (date(2024, 1, 1), dec!(310)),
+            (date(2024, 2, 1), dec!(384.57)),
+            (date(2024, 3, 1), dec!(505.43)),
+        ]);
+        assert_eq!(modified.total, dec!(1200));
+        assert_eq!(modified.deferred(), dec!(1200));
+        assert_eq!(modified.segment_start, date(2024, 2, 15));
+    }
+
+    #[test]
+    fn test_modification_split_by_unrecognized_balance() {
+        let recognizer = RevenueRecognizer::new();
+        let subscription_id = Uuid::new_v4();
+        recognizer.schedule_invoice(&invoice(subscription_id, date(2024, 1, 1), date(2024, 4, 1), vec![
+            item("Pro Plan - Quarterly", ItemType::Subscription, dec!(600)),
+            item("Seats", ItemType::Proration, dec!(300)),
+        ]));
+
+        let modified = recognizer.modify(&modification(subscription_id, date(2024, 1, 1), dec!(90), None)).unwrap();
+        let mut totals: Vec<Decimal> = modified.iter().map(|s| s.total).collect();
+        totals.sort();
+        assert_eq!(totals, vec![dec!(330), dec!(660)]);
+    }
+
+    #[test]
+    fn test_invalid_modifications() {
+        let recognizer = RevenueRecognizer::new();
+        let (subscription_id, _) = quarter(&recognizer);
+
+        let unknown = modification(Uuid::new_v4(), date(2024, 2, 1), dec!(10), None);
+        assert!(matches!(recognizer.modify(&unknown), Err(RevenueError::NoOpenSchedules)));
+
+        let early = modification(subscription_id, date(2023, 12, 31), dec!(10), None);
+        assert!(matches!(recognizer.modify(&early), Err(RevenueError::InvalidModification(_))));
+
+        let inverted = modification(subscription_id, date(2024, 2, 15), dec!(0), Some(date(2024, 2, 1)));
+        assert!(matches!(recognizer.modify(&inverted), Err(RevenueError::InvalidModification(_))));
+
+        recognizer.recognize_through(date(2024, 2, 29));
+        let closed = modification(subscription_id, date(2024, 2, 15), dec!(10), None);
+        assert!(matches!(recognizer.modify(&closed), Err(RevenueError::PeriodClosed(month)) if month == date(2024, 2, 1)));
+    }
+
+    #[test]
+    fn test_journal_export() {
+        let recognizer = RevenueRecognizer::new();
+        recognizer.schedule_invoice(&invoice(Uuid::new_v4(), date(2024, 1, 1), date(2024, 2, 1), vec![
+            item("Pro Plan, \"Monthly\"", ItemType::Subscription, dec!(99)),
+        ]));
+        recognizer.recognize_through(date(2024, 1, 31));
+
+        let csv = String::from_utf8(recognizer.export_journal(date(2024, 1, 1), date(2024, 1, 31), JournalFormat::Csv)).unwrap();
+        let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("date,entry_id,kind,"));
+        assert!(rows[1].starts_with("2024-01-01,"));
+        assert!(rows[1].ends_with(",99,USD,\"Pro Plan, \"\"Monthly\"\"\""));
+        assert!(rows[2].contains(",recognition,"));
+
+        let json: Vec<JournalEntry> = serde_json::from_slice(
+            &recognizer.export_journal(date(2024, 1, 31), date(2024, 1, 31), JournalFormat::Json),
+        ).unwrap();
+        assert_eq!(json.len(), 1);
+        assert_eq!(json[0].kind, JournalKind::Recognition);
+    }
+}
@@ -0,0 +1,106 @@
+//! Sandbox (Test Mode) Tenants
+//!
+//! Sandbox tenants run on a simulated clock that only moves when advanced,
+//! so integrators can fast-forward through billing cycles. Their payments
+//! route to Stripe test mode and their invoices and subscriptions are kept
+//! out of revenue reporting.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+
+/// Registry of sandbox tenants and their simulated clocks
+pub struct SandboxRegistry {
+    tenants: Arc<RwLock<HashMap<Uuid, SandboxTenant>>>,
+}
+
+impl SandboxRegistry {
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self {
+            tenants: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Flag a tenant as sandbox; its clock starts at the current time
+    pub fn enroll(&self, tenant_id: Uuid) -> SandboxTenant {
+        self.tenants.write()
+            .entry(tenant_id)
+            .or_insert_with(|| {
+                let now = Utc::now();
+                SandboxTenant { tenant_id, clock: now, enrolled_at: now }
+            })
+            .clone()
+    }
+
+    /// Whether the tenant is a sandbox tenant
+    pub fn is_sandbox(&self, tenant_id: Uuid) -> bool {
+        self.tenants.read().contains_key(&tenant_id)
+    }
+
+    /// Current time for a tenant: simulated for sandbox tenants, real otherwise
+    pub fn now(&self, tenant_id: Uuid) -> DateTime<Utc> {
+        self.tenants.read()
+            .get(&tenant_id)
+            .map(|t| t.clock)
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// Move a sandbox tenant's clock forward
+    pub fn advance(&self, tenant_id: Uuid, by: Duration) -> Result<DateTime<Utc>, SandboxError> {
+        if by <= Duration::zero() {
+            return Err(SandboxError::InvalidAdvance);
+        }
+        let mut tenants = self.tenants.write();
+        let tenant = tenants.get_mut(&tenant_id).ok_or(SandboxError::NotSandbox)?;
+        tenant.clock += by;
+        tracing::debug!("Sandbox clock for {} advanced to {}", tenant_id, tenant.clock);
+        Ok(tenant.clock)
+    }
+
+    /// Get sandbox tenant
+    pub fn get(&self, tenant_id: Uuid) -> Option<SandboxTenant> {
+        self.tenants.read().get(&tenant_id).cloned()
+    }
+
+    /// Remove sandbox flag
+    pub fn remove(&self, tenant_id: Uuid) -> Option<SandboxTenant> {
+        self.tenants.write().remove(&tenant_id)
+    }
+}
+
+impl Default for SandboxRegistry {
+    fn default() -> Self { Self::new() }
+}
+
+/// Sandbox tenant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxTenant {
+    /// Tenant ID
+    pub tenant_id: Uuid,
+    /// Simulated current time (frozen between advances)
+    pub clock: DateTime<Utc>,
+    /// Real time the tenant was enrolled
+    pub enrolled_at: DateTime<Utc>,
+}
+
+/// Sandbox error
+#[derive(Debug, Clone)]
+pub enum SandboxError {
+    /// Tenant is not a sandbox tenant
+    NotSandbox,
+    /// Clock can only move forward
+    InvalidAdvance,
+}
+
+impl std::fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSandbox => write!(f, "Tenant is not a sandbox tenant"),
+            Self::InvalidAdvance => write!(f, "Sandbox clock can only move forward"),
+        }
+    }
+}
@@ -9,25 +9,50 @@ use rust_decimal_macros::dec;
 use uuid::Uuid;
 use chrono::{DateTime, Utc, NaiveDate};
 
+use crate::dunning::{DunningEvent, DunningNotifier};
+use crate::pricing::PricingEngine;
+use crate::sandbox::SandboxRegistry;
+
 /// Subscription manager
 pub struct SubscriptionManager {
     subscriptions: Arc<RwLock<HashMap<Uuid, Subscription>>>,
+    sandbox: Arc<SandboxRegistry>,
+    pricing: Arc<PricingEngine>,
+    proration: ProrationConfig,
 }
 
 impl SubscriptionManager {
     pub fn new() -> Self {
         Self {
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            sandbox: Arc::new(SandboxRegistry::new()),
+            pricing: Arc::new(PricingEngine::new()),
+            proration: ProrationConfig::default(),
         }
     }
 
+    /// Use shared sandbox registry (simulated clocks, MRR exclusion)
+    pub fn with_sandbox(mut self, sandbox: Arc<SandboxRegistry>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Use shared pricing engine (plan prices for proration)
+    pub fn with_pricing(mut self, pricing: Arc<PricingEngine>) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Set proration rounding and time basis
+    pub fn with_proration(mut self, proration: ProrationConfig) -> Self {
+        self.proration = proration;
+        self
+    }
+
     /// Create subscription
     pub fn create(&self, tenant_id: Uuid, plan_id: &str, billing_period: BillingPeriod) -> Subscription {
-        let now = Utc::now();
-        let period_end = match billing_period {
-            BillingPeriod::Monthly => now + chrono::Duration::days(30),
-            BillingPeriod::Annual => now + chrono::Duration::days(365),
-        };
+        let now = self.sandbox.now(tenant_id);
+        let period_end = now + billing_period.length();
 
         let subscription = Subscription {
             id: Uuid::new_v4(),
@@ -41,6 +66,10 @@ impl SubscriptionManager {
             cancel_at_period_end: false,
             canceled_at: None,
             created_at: now,
+            sandbox: self.sandbox.is_sandbox(tenant_id),
+            billed_plan_id: None,
+            pending_prorations: Vec::new(),
+            grace_period_end: None,
         };
 
         self.subscriptions.write().insert(subscription.id, subscription.clone());
@@ -61,17 +90,32 @@ impl SubscriptionManager {
     }
 
     /// Change plan (upgrade/downgrade)
+    ///
+    /// With `prorate`, the unused time on the old plan is credited and the
+    /// remainder of the period is charged at the new plan's price. Both
+    /// items are billed on the period's invoice, whose base charge stays on
+    /// the plan the period started with.
     pub fn change_plan(&self, id: Uuid, new_plan_id: &str, prorate: bool) -> Result<PlanChange, SubscriptionError> {
         let mut subs = self.subscriptions.write();
         let sub = subs.get_mut(&id).ok_or(SubscriptionError::NotFound)?;
 
+        if self.pricing.get_plan(new_plan_id).is_none() {
+            return Err(SubscriptionError::InvalidPlan);
+        }
+
+        let now = self.sandbox.now(sub.tenant_id);
         let old_plan = sub.plan_id.clone();
-        let proration_amount = if prorate {
-            self.calculate_proration(sub, new_plan_id)
+        let items = if prorate && sub.status == SubscriptionStatus::Active {
+            self.calculate_proration(sub, new_plan_id, now)?
         } else {
-            dec!(0)
+            Vec::new()
         };
+        let proration_amount = items.iter().map(|i| i.amount).sum();
 
+        if !items.is_empty() {
+            sub.billed_plan_id.get_or_insert_with(|| old_plan.clone());
+            sub.pending_prorations.extend(items.iter().cloned());
+        }
         sub.plan_id = new_plan_id.into();
 
         Ok(PlanChange {
@@ -79,24 +123,56 @@ impl SubscriptionManager {
             old_plan,
             new_plan: new_plan_id.into(),
             proration_amount,
-            effective_at: Utc::now(),
+            proration_items: items,
+            effective_at: now,
         })
     }
 
-    fn calculate_proration(&self, sub: &Subscription, new_plan: &str) -> Decimal {
-        // Simplified proration calculation
-        let days_remaining = (sub.current_period_end - Utc::now()).num_days() as f64;
-        let total_days = (sub.current_period_end - sub.current_period_start).num_days() as f64;
-        let ratio = days_remaining / total_days;
+    fn calculate_proration(&self, sub: &Subscription, new_plan: &str, now: DateTime<Utc>) -> Result<Vec<ProrationItem>, SubscriptionError> {
+        let old_price = self.period_price(sub, &sub.plan_id)?;
+        let new_price = self.period_price(sub, new_plan)?;
+        let fraction = self.proration.unused_fraction(sub.current_period_start, sub.current_period_end, now);
 
-        // In production: look up actual plan prices
-        let old_price = dec!(99); // Placeholder
-        let new_price = dec!(499); // Placeholder
+        if fraction <= dec!(0) {
+            return Ok(Vec::new());
+        }
 
-        let credit = old_price * Decimal::from_f64_retain(ratio).unwrap_or(dec!(0));
-        let charge = new_price * Decimal::from_f64_retain(ratio).unwrap_or(dec!(0));
+        let credit = -self.proration.round(old_price * fraction);
+        let charge = self.proration.round(new_price * fraction);
+        let percent = (fraction * dec!(100)).round_dp(1);
 
-        charge - credit
+        let item = |plan_id: &str, amount: Decimal, description: String| ProrationItem {
+            id: Uuid::new_v4(),
+            plan_id: plan_id.into(),
+            description,
+            fraction,
+            amount,
+            period_start: now,
+            period_end: sub.current_period_end,
+            created_at: now,
+        };
+
+        Ok(vec![
+            item(&sub.plan_id, credit, format!("Unused time on {} ({}% of period)", sub.plan_id, percent)),
+            item(new_plan, charge, format!("Remaining time on {} ({}% of period)", new_plan, percent)),
+        ])
+    }
+
+    /// Plan price for one of the subscription's billing periods
+    fn period_price(&self, sub: &Subscription, plan_id: &str) -> Result<Decimal, SubscriptionError> {
+        let monthly = self.pricing.base_price(sub.tenant_id, plan_id)
+            .ok_or(SubscriptionError::InvalidPlan)?;
+        Ok(match sub.billing_period {
+            BillingPeriod::Monthly => monthly,
+            BillingPeriod::Annual => monthly * dec!(12),
+        })
+    }
+
+    /// Remove prorations that have been invoiced
+    pub fn clear_prorations(&self, id: Uuid, invoiced: &[Uuid]) {
+        if let Some(sub) = self.subscriptions.write().get_mut(&id) {
+            sub.pending_prorations.retain(|p| !invoiced.contains(&p.id));
+        }
     }
 
     /// Cancel subscription
@@ -108,7 +184,7 @@ impl SubscriptionManager {
             sub.cancel_at_period_end = true;
         } else {
             sub.status = SubscriptionStatus::Canceled;
-            sub.canceled_at = Some(Utc::now());
+            sub.canceled_at = Some(self.sandbox.now(sub.tenant_id));
         }
 
         Ok(sub.clone())
@@ -132,7 +208,7 @@ impl SubscriptionManager {
 
     /// Start trial
     pub fn start_trial(&self, tenant_id: Uuid, plan_id: &str, days: u32) -> Subscription {
-        let now = Utc::now();
+        let now = self.sandbox.now(tenant_id);
         let trial_end = now + chrono::Duration::days(days as i64);
 
         let subscription = Subscription {
@@ -147,18 +223,105 @@ impl SubscriptionManager {
             cancel_at_period_end: false,
             canceled_at: None,
             created_at: now,
+            sandbox: self.sandbox.is_sandbox(tenant_id),
+            billed_plan_id: None,
+            pending_prorations: Vec::new(),
+            grace_period_end: None,
         };
 
         self.subscriptions.write().insert(subscription.id, subscription.clone());
         subscription
     }
 
-    /// Calculate MRR
+    /// Roll a tenant's subscriptions through every period that ended by `now`
+    ///
+    /// Returns one cycle per elapsed paid period, oldest first. Trials
+    /// convert to active without a billable cycle, and subscriptions set to
+    /// cancel at period end are canceled.
+    pub fn advance_periods(&self, tenant_id: Uuid, now: DateTime<Utc>) -> Vec<BillingCycle> {
+        let mut subs = self.subscriptions.write();
+        let mut cycles = Vec::new();
+
+        for sub in subs.values_mut().filter(|s| s.tenant_id == tenant_id) {
+            while sub.current_period_end <= now {
+                match sub.status {
+                    SubscriptionStatus::Trialing => {
+                        sub.status = SubscriptionStatus::Active;
+                    }
+                    // Past-due subscriptions keep billing during the grace period
+                    SubscriptionStatus::Active | SubscriptionStatus::PastDue => {
+                        // Prorations belong to the period they were made in
+                        let mut snapshot = sub.clone();
+                        snapshot.pending_prorations = std::mem::take(&mut sub.pending_prorations);
+                        sub.billed_plan_id = None;
+                        cycles.push(BillingCycle {
+                            subscription: snapshot,
+                            period_start: sub.current_period_start,
+                            period_end: sub.current_period_end,
+                        });
+                        if sub.cancel_at_period_end {
+                            sub.status = SubscriptionStatus::Canceled;
+                            sub.canceled_at = Some(sub.current_period_end);
+                            break;
+                        }
+                    }
+                    _ => break,
+                }
+                sub.current_period_start = sub.current_period_end;
+                sub.current_period_end = sub.current_period_start + sub.billing_period.length();
+            }
+        }
+
+        cycles.sort_by_key(|c| c.period_end);
+        cycles
+    }
+
+    /// Mark a tenant's live subscriptions past due until `grace_until`
+    pub fn mark_past_due(&self, tenant_id: Uuid, grace_until: DateTime<Utc>) {
+        for sub in self.subscriptions.write().values_mut().filter(|s| s.tenant_id == tenant_id) {
+            if sub.status == SubscriptionStatus::Active {
+                sub.status = SubscriptionStatus::PastDue;
+                sub.grace_period_end = Some(grace_until);
+            }
+        }
+    }
+
+    /// Suspend a tenant's delinquent subscriptions
+    pub fn suspend(&self, tenant_id: Uuid) {
+        for sub in self.subscriptions.write().values_mut().filter(|s| s.tenant_id == tenant_id) {
+            if matches!(sub.status, SubscriptionStatus::Active | SubscriptionStatus::PastDue) {
+                sub.status = SubscriptionStatus::Suspended;
+            }
+        }
+    }
+
+    /// Restore a tenant's past-due or suspended subscriptions after payment
+    pub fn restore(&self, tenant_id: Uuid) {
+        for sub in self.subscriptions.write().values_mut().filter(|s| s.tenant_id == tenant_id) {
+            if matches!(sub.status, SubscriptionStatus::PastDue | SubscriptionStatus::Suspended) {
+                sub.status = SubscriptionStatus::Active;
+                sub.grace_period_end = None;
+            }
+        }
+    }
+
+    /// Cancel a tenant's delinquent subscriptions immediately
+    pub fn cancel_delinquent(&self, tenant_id: Uuid, at: DateTime<Utc>) {
+        for sub in self.subscriptions.write().values_mut().filter(|s| s.tenant_id == tenant_id) {
+            if matches!(sub.status, SubscriptionStatus::Active | SubscriptionStatus::PastDue | SubscriptionStatus::Suspended) {
+                sub.status = SubscriptionStatus::Canceled;
+                sub.canceled_at = Some(at);
+                sub.grace_period_end = None;
+            }
+        }
+    }
+
+    /// Calculate MRR (sandbox subscriptions excluded)
     pub fn calculate_mrr(&self) -> Decimal {
         // In production: sum of all active subscription amounts normalized to monthly
         let subs = self.subscriptions.read();
         let active_count = subs.values()
-            .filter(|s| s.status == SubscriptionStatus::Active)
+            .filter(|s| s.status == SubscriptionStatus::Active && !s.sandbox)
             .count();
 
         // Placeholder: assume average of $200/month
@@ -175,6 +338,22 @@ impl Default for SubscriptionManager {
     fn default() -> Self { Self::new() }
 }
 
+/// Dunning drives subscription state: past due during the grace period,
+/// then suspended or canceled, and active again once paid
+impl DunningNotifier for SubscriptionManager {
+    fn notify(&self, event: &DunningEvent) {
+        match event {
+            DunningEvent::GracePeriodStarted { tenant_id, grace_until, .. } => {
+                self.mark_past_due(*tenant_id, *grace_until);
+            }
+            DunningEvent::Suspended { tenant_id, .. } => self.suspend(*tenant_id),
+            DunningEvent::Canceled { tenant_id, at, .. } => self.cancel_delinquent(*tenant_id, *at),
+            DunningEvent::Recovered { tenant_id, .. } => self.restore(*tenant_id),
+            _ => {}
+        }
+    }
+}
+
 /// Subscription
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Subscription {
@@ -189,6 +368,26 @@ pub struct Subscription {
     pub cancel_at_period_end: bool,
     pub canceled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    /// Belongs to a sandbox tenant
+    #[serde(default)]
+    pub sandbox: bool,
+    /// Plan the current period's base charge is billed on, when the plan
+    /// was changed with proration mid-period
+    #[serde(default)]
+    pub billed_plan_id: Option<String>,
+    /// Proration items not yet invoiced
+    #[serde(default)]
+    pub pending_prorations: Vec<ProrationItem>,
+    /// End of the dunning grace period while past due
+    #[serde(default)]
+    pub grace_period_end: Option<DateTime<Utc>>,
+}
+
+impl Subscription {
+    /// Plan the current period's base charge is billed on
+    pub fn billed_plan(&self) -> &str {
+        self.billed_plan_id.as_deref().unwrap_or(&self.plan_id)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -198,6 +397,8 @@ pub enum SubscriptionStatus {
     PastDue,
     Canceled,
     Unpaid,
+    /// Service suspended for non-payment
+    Suspended,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -206,6 +407,27 @@ pub enum BillingPeriod {
     Annual,
 }
 
+impl BillingPeriod {
+    /// Length of one period
+    pub fn length(&self) -> chrono::Duration {
+        match self {
+            Self::Monthly => chrono::Duration::days(30),
+            Self::Annual => chrono::Duration::days(365),
+        }
+    }
+}
+
+/// Completed billing period awaiting an invoice
+#[derive(Debug, Clone)]
+pub struct BillingCycle {
+    /// Subscription as of the period
+    pub subscription: Subscription,
+    /// Period start
+    pub period_start: DateTime<Utc>,
+    /// Period end
+    pub period_end: DateTime<Utc>,
+}
+
 /// Plan change
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanChange {
@@ -213,9 +435,108 @@ pub struct PlanChange {
     pub old_plan: String,
     pub new_plan: String,
     pub proration_amount: Decimal,
+    /// Credit and charge items behind `proration_amount`
+    #[serde(default)]
+    pub proration_items: Vec<ProrationItem>,
     pub effective_at: DateTime<Utc>,
 }
 
+/// Prorated charge (positive) or credit (negative) from a plan change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProrationItem {
+    /// Item ID
+    pub id: Uuid,
+    /// Plan credited or charged
+    pub plan_id: String,
+    /// Invoice line description
+    pub description: String,
+    /// Share of the billing period covered
+    pub fraction: Decimal,
+    /// Rounded amount (negative for credits)
+    pub amount: Decimal,
+    /// Start of the prorated span (the plan change)
+    pub period_start: DateTime<Utc>,
+    /// End of the prorated span (period end)
+    pub period_end: DateTime<Utc>,
+    /// When the item was created
+    pub created_at: DateTime<Utc>,
+}
+
+/// Proration rounding and time basis
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ProrationConfig {
+    /// Rounding applied to each prorated line item
+    pub rounding: ProrationRounding,
+    /// Decimal places amounts are rounded to
+    pub decimal_places: u32,
+    /// Unit the unused share of the period is measured in
+    pub granularity: ProrationGranularity,
+}
+
+impl Default for ProrationConfig {
+    fn default() -> Self {
+        Self {
+            rounding: ProrationRounding::HalfUp,
+            decimal_places: 2,
+            granularity: ProrationGranularity::Second,
+        }
+    }
+}
+
+impl ProrationConfig {
+    /// Unused share of the period at `at`, in [0, 1]
+    pub fn unused_fraction(&self, start: DateTime<Utc>, end: DateTime<Utc>, at: DateTime<Utc>) -> Decimal {
+        let (total, remaining) = match self.granularity {
+            ProrationGranularity::Second => ((end - start).num_seconds(), (end - at).num_seconds()),
+            // Partially used days are not credited
+            ProrationGranularity::Day => ((end - start).num_days(), (end - at).num_days()),
+        };
+        if total <= 0 {
+            return dec!(0);
+        }
+        (Decimal::from(remaining.clamp(0, total)) / Decimal::from(total)).round_dp(6)
+    }
+
+    /// Round an amount per the configured rule
+    pub fn round(&self, amount: Decimal) -> Decimal {
+        amount.round_dp_with_strategy(self.decimal_places, self.rounding.strategy())
+    }
+}
+
+/// Rounding rule for prorated amounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProrationRounding {
+    /// Half away from zero
+    HalfUp,
+    /// Half to even (banker's rounding)
+    HalfEven,
+    /// Away from zero
+    Up,
+    /// Toward zero
+    Down,
+}
+
+impl ProrationRounding {
+    fn strategy(&self) -> rust_decimal::RoundingStrategy {
+        use rust_decimal::RoundingStrategy;
+        match self {
+            Self::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::Up => RoundingStrategy::AwayFromZero,
+            Self::Down => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+/// Time unit proration is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProrationGranularity {
+    /// Exact to the second
+    Second,
+    /// Whole days remaining
+    Day,
+}
+
 /// Subscription error
 #[derive(Debug, Clone)]
 pub enum SubscriptionError {
@@ -235,3 +556,163 @@ impl std::fmt::Display for SubscriptionError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn rounding(rounding: ProrationRounding, decimal_places: u32) -> ProrationConfig {
+        ProrationConfig { rounding, decimal_places, ..ProrationConfig::default() }
+    }
+
+    /// Manager for a sandbox tenant, so the clock only moves when told to
+    fn sandboxed() -> (SubscriptionManager, Arc<SandboxRegistry>, Uuid) {
+        let sandbox = Arc::new(SandboxRegistry::new());
+        let tenant_id = Uuid::new_v4();
+        sandbox.enroll(tenant_id);
+        (SubscriptionManager::new().with_sandbox(sandbox.clone()), sandbox, tenant_id)
+    }
+
+    #[test]
+    fn test_rounding_rules() {
+        let half_up = rounding(ProrationRounding::HalfUp, 2);
+        assert_eq!(half_up.round(dec!(10.005)), dec!(10.01));
+        assert_eq!(half_up.round(dec!(10.004)), dec!(10.00));
+        assert_eq!(half_up.round(dec!(-10.005)), dec!(-10.01));
+
+        let half_even = rounding(ProrationRounding::HalfEven, 2);
+        assert_eq!(half_even.round(dec!(10.005)), dec!(10.00));
+        assert_eq!(half_even.round(dec!(10.015)), dec!(10.02));
+
+        let up = rounding(ProrationRounding::Up, 2);
+        assert_eq!(up.round(dec!(10.001)), dec!(10.01));
+        assert_eq!(up.round(dec!(-10.001)), dec!(-10.01));
+
+        let down = rounding(ProrationRounding::Down, 2);
+        assert_eq!(down.round(dec!(10.009)), dec!(10.00));
+        assert_eq!(down.round(dec!(-10.009)), dec!(-10.00));
+    }
+
+    #[test]
+    fn test_rounding_to_currency_minor_units() {
+        // JPY has no minor unit, BHD has three
+        let yen = rounding(ProrationRounding::HalfUp, 0);
+        assert_eq!(yen.round(dec!(1234.5)), dec!(1235));
+        assert_eq!(rounding(ProrationRounding::HalfEven, 0).round(dec!(1234.5)), dec!(1234));
+
+        let dinar = rounding(ProrationRounding::HalfUp, 3);
+        assert_eq!(dinar.round(dec!(1.2345)), dec!(1.235));
+        assert_eq!(dinar.round(dec!(1.2)), dec!(1.2));
+
+        assert_eq!(ProrationConfig::default().round(dec!(99.99)), dec!(99.99));
+    }
+
+    #[test]
+    fn test_rounding_zero_and_negative_amounts() {
+        for rule in [ProrationRounding::HalfUp, ProrationRounding::HalfEven, ProrationRounding::Up, ProrationRounding::Down] {
+            let config = rounding(rule, 2);
+            assert_eq!(config.round(dec!(0)), dec!(0));
+            // Credits round symmetrically with charges
+            assert_eq!(config.round(dec!(-49.995)), -config.round(dec!(49.995)));
+        }
+        assert_eq!(rounding(ProrationRounding::HalfUp, 2).round(dec!(-0.004)), dec!(0));
+    }
+
+    #[test]
+    fn test_unused_fraction_boundaries() {
+        let config = ProrationConfig::default();
+        let start = Utc::now();
+        let end = start + Duration::days(30);
+
+        assert_eq!(config.unused_fraction(start, end, start), dec!(1));
+        assert_eq!(config.unused_fraction(start, end, start + Duration::days(15)), dec!(0.5));
+        assert_eq!(config.unused_fraction(start, end, end), dec!(0));
+        // Outside the period the share is clamped
+        assert_eq!(config.unused_fraction(start, end, end + Duration::days(1)), dec!(0));
+        assert_eq!(config.unused_fraction(start, end, start - Duration::days(1)), dec!(1));
+        // Empty or inverted periods have nothing to prorate
+        assert_eq!(config.unused_fraction(start, start, start), dec!(0));
+        assert_eq!(config.unused_fraction(end, start, start), dec!(0));
+
+        // By day, the partly used day is not credited
+        let daily = ProrationConfig { granularity: ProrationGranularity::Day, ..config };
+        let at = start + Duration::days(1) + Duration::hours(12);
+        assert_eq!(daily.unused_fraction(start, end, at), dec!(0.933333));
+    }
+
+    #[test]
+    fn test_upgrade_mid_period() {
+        let (manager, sandbox, tenant_id) = sandboxed();
+        let sub = manager.create(tenant_id, "pro", BillingPeriod::Monthly);
+        sandbox.advance(tenant_id, Duration::days(15)).unwrap();
+
+        let change = manager.change_plan(sub.id, "enterprise", true).unwrap();
+        let amounts: Vec<Decimal> = change.proration_items.iter().map(|i| i.amount).collect();
+        assert_eq!(amounts, vec![dec!(-49.50), dec!(249.50)]);
+        assert_eq!(change.proration_amount, dec!(200.00));
+        assert!(change.proration_items.iter().all(|i| i.fraction == dec!(0.5)));
+        assert_eq!(change.proration_items[0].period_end, sub.current_period_end);
+
+        // The period's base charge stays on the old plan
+        let sub = manager.get(sub.id).unwrap();
+        assert_eq!(sub.plan_id, "enterprise");
+        assert_eq!(sub.billed_plan(), "pro");
+        assert_eq!(sub.pending_prorations.len(), 2);
+    }
+
+    #[test]
+    fn test_downgrade_to_free_plan_is_a_net_credit() {
+        let (manager, sandbox, tenant_id) = sandboxed();
+        let sub = manager.create(tenant_id, "pro", BillingPeriod::Monthly);
+        sandbox.advance(tenant_id, Duration::days(20)).unwrap();
+
+        let change = manager.change_plan(sub.id, "free", true).unwrap();
+        let amounts: Vec<Decimal> = change.proration_items.iter().map(|i| i.amount).collect();
+        assert_eq!(amounts, vec![dec!(-33.00), dec!(0)]);
+        assert_eq!(change.proration_amount, dec!(-33.00));
+    }
+
+    #[test]
+    fn test_annual_period_prorates_the_annual_price() {
+        let (manager, sandbox, tenant_id) = sandboxed();
+        let sub = manager.create(tenant_id, "pro", BillingPeriod::Annual);
+        sandbox.advance(tenant_id, Duration::days(73)).unwrap();
+
+        // 292 of 365 days left: 0.8 of 12 x 99
+        let change = manager.change_plan(sub.id, "free", true).unwrap();
+        assert_eq!(change.proration_amount, dec!(-950.40));
+    }
+
+    #[test]
+    fn test_no_proration_at_period_end_or_without_prorate() {
+        let (manager, sandbox, tenant_id) = sandboxed();
+        let sub = manager.create(tenant_id, "pro", BillingPeriod::Monthly);
+        let unprorated = manager.change_plan(sub.id, "enterprise", false).unwrap();
+        assert!(unprorated.proration_items.is_empty());
+        assert_eq!(unprorated.proration_amount, dec!(0));
+
+        sandbox.advance(tenant_id, Duration::days(30)).unwrap();
+        let change = manager.change_plan(sub.id, "pro", true).unwrap();
+        assert!(change.proration_items.is_empty());
+        assert_eq!(manager.get(sub.id).unwrap().billed_plan(), "pro");
+
+        assert!(matches!(manager.change_plan(sub.id, "platinum", true), Err(SubscriptionError::InvalidPlan)));
+    }
+
+    #[test]
+    fn test_prorations_move_to_the_cycle_they_were_made_in() {
+        let (manager, sandbox, tenant_id) = sandboxed();
+        let sub = manager.create(tenant_id, "pro", BillingPeriod::Monthly);
+        sandbox.advance(tenant_id, Duration::days(10)).unwrap();
+        manager.change_plan(sub.id, "enterprise", true).unwrap();
+
+        let cycles = manager.advance_periods(tenant_id, sub.current_period_end + Duration::days(30));
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].subscription.pending_prorations.len(), 2);
+        assert_eq!(cycles[0].subscription.billed_plan(), "pro");
+        assert!(cycles[1].subscription.pending_prorations.is_empty());
+        assert_eq!(cycles[1].subscription.billed_plan(), "enterprise");
+        assert_eq!(cycles[1].period_start, cycles[0].period_end);
+    }
+}
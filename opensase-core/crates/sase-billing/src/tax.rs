@@ -0,0 +1,746 @@
+//! Tax Calculation
+//!
+//! Computes VAT/GST/sales tax per invoice line from the tenant's billing
+//! address. EU and UK business customers with a VAT ID are reverse-charged
+//! when the seller is established elsewhere, and exemption certificates
+//! zero-rate lines in their jurisdiction. Rates come from a [`TaxProvider`];
+//! the built-in table covers headline rates, and an external service
+//! (Avalara, ...) can be plugged in for local rates and filing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use uuid::Uuid;
+use chrono::NaiveDate;
+
+/// Tenant billing address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BillingAddress {
+    /// Street address
+    pub line1: String,
+    /// City
+    pub city: String,
+    /// State, province or region code (e.g. `NY`, `ON`)
+    pub region: Option<String>,
+    /// Postal code
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+}
+
+/// Tenant tax registration and exemptions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxProfile {
+    /// Tenant
+    pub tenant_id: Uuid,
+    /// Billing address
+    pub address: BillingAddress,
+    /// VAT/GST registration number
+    pub tax_id: Option<String>,
+    /// Purchasing as a business (B2B)
+    pub business: bool,
+    /// Exemption certificates on file
+    pub exemptions: Vec<ExemptionCertificate>,
+}
+
+/// Tax exemption certificate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExemptionCertificate {
+    /// Certificate ID
+    pub id: Uuid,
+    /// Jurisdiction covered: country (`US`) or country-region (`US-NY`)
+    pub jurisdiction: String,
+    /// Issuer's certificate number
+    pub certificate_number: String,
+    /// Exemption reason (resale, non-profit, government, ...)
+    pub reason: String,
+    /// First day valid
+    pub valid_from: NaiveDate,
+    /// Last day valid
+    pub valid_until: Option<NaiveDate>,
+}
+
+impl ExemptionCertificate {
+    /// Whether the certificate covers a jurisdiction on a date
+    pub fn covers(&self, jurisdiction: &str, date: NaiveDate) -> bool {
+        let in_force = self.valid_from <= date && self.valid_until.is_none_or(|u| date <= u);
+        let matches = jurisdiction == self.jurisdiction
+            || jurisdiction.starts_with(&format!("{}-", self.jurisdiction));
+        in_force && matches
+    }
+}
+
+/// What a line is for (drives taxability)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TaxCategory {
+    /// SaaS subscription or usage
+    DigitalService,
+    /// Professional services
+    Services,
+    /// Never taxed (credits, refunds)
+    NonTaxable,
+}
+
+/// Kind of tax charged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TaxType {
+    /// Value added tax
+    Vat,
+    /// Goods and services tax
+    Gst,
+    /// Harmonized sales tax (Canada)
+    Hst,
+    /// Provincial sales tax (Canada)
+    Pst,
+    /// Quebec sales tax
+    Qst,
+    /// US state sales tax
+    SalesTax,
+    /// Consumption tax (Japan)
+    ConsumptionTax,
+    /// No tax applies
+    None,
+}
+
+/// Line to tax
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxableLine {
+    /// Index of the invoice line
+    pub index: usize,
+    /// Line description
+    pub description: String,
+    /// Amount after discounts and credits
+    pub amount: Decimal,
+    /// Taxability category
+    pub category: TaxCategory,
+}
+
+/// Tax calculation request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxRequest {
+    /// Buyer profile
+    pub profile: TaxProfile,
+    /// Invoice date (rates and certificates are evaluated on it)
+    pub date: NaiveDate,
+    /// Currency
+    pub currency: String,
+    /// Lines to tax
+    pub lines: Vec<TaxableLine>,
+}
+
+/// Tax on one line and one component (a line can carry GST + PST)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineTax {
+    /// Index of the invoice line
+    pub index: usize,
+    /// Jurisdiction (`DE`, `US-NY`, `CA-BC`)
+    pub jurisdiction: String,
+    /// Tax type
+    pub tax_type: TaxType,
+    /// Rate as a fraction (0.19 = 19%)
+    pub rate: Decimal,
+    /// Amount taxed
+    pub taxable_amount: Decimal,
+    /// Tax charged
+    pub tax_amount: Decimal,
+    /// Why no tax was charged, if zero-rated
+    pub exempt_reason: Option<String>,
+}
+
+/// Tax totals per jurisdiction and type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxSummary {
+    /// Jurisdiction
+    pub jurisdiction: String,
+    /// Tax type
+    pub tax_type: TaxType,
+    /// Rate as a fraction
+    pub rate: Decimal,
+    /// Amount taxed
+    pub taxable_amount: Decimal,
+    /// Tax charged
+    pub tax_amount: Decimal,
+}
+
+/// Tax result attached to an invoice
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaxBreakdown {
+    /// Per-line taxes
+    pub lines: Vec<LineTax>,
+    /// Totals per jurisdiction/type/rate
+    pub summary: Vec<TaxSummary>,
+    /// Total tax
+    pub total_tax: Decimal,
+    /// Reverse charge applies; the buyer accounts for VAT
+    pub reverse_charge: bool,
+    /// Buyer's tax ID as printed on the invoice
+    pub customer_tax_id: Option<String>,
+    /// Legal notes required on the invoice
+    pub notes: Vec<String>,
+    /// Provider that computed the breakdown
+    pub provider: String,
+}
+
+impl TaxBreakdown {
+    /// Breakdown for an invoice that is not taxed
+    pub fn untaxed(note: &str) -> Self {
+        Self {
+            notes: vec![note.to_string()],
+            provider: "none".into(),
+            ..Default::default()
+        }
+    }
+
+    /// Tax charged on one invoice line
+    pub fn line_tax(&self, index: usize) -> Decimal {
+        self.lines.iter().filter(|l| l.index == index).map(|l| l.tax_amount).sum()
+    }
+
+    fn summarize(&mut self) {
+        let mut totals: HashMap<(String, TaxType, Decimal), (Decimal, Decimal)> = HashMap::new();
+        for line in &self.lines {
+            let entry = totals.entry((line.jurisdiction.clone(), line.tax_type, line.rate)).or_default();
+            entry.0 += line.taxable_amount;
+            entry.1 += line.tax_amount;
+        }
+        self.summary = totals.into_iter()
+            .map(|((jurisdiction, tax_type, rate), (taxable_amount, tax_amount))| TaxSummary {
+                jurisdiction, tax_type, rate, taxable_amount, tax_amount,
+            })
+            .collect();
+        self.summary.sort_by(|a, b| a.jurisdiction.cmp(&b.jurisdiction));
+        self.total_tax = self.lines.iter().map(|l| l.tax_amount).sum();
+    }
+}
+
+/// Source of tax rates and rules
+pub trait TaxProvider: Send + Sync {
+    /// Provider name (recorded on the breakdown)
+    fn name(&self) -> &str;
+    /// Compute taxes for an invoice
+    fn calculate(&self, request: &TaxRequest) -> Result<TaxBreakdown, TaxError>;
+}
+
+// =============================================================================
+// Built-in rates
+// =============================================================================
+
+/// Where the seller is established
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SellerConfig {
+    /// ISO country code of the selling entity
+    pub country: String,
+    /// US states with sales tax nexus (others are not collected)
+    pub us_nexus: Vec<String>,
+}
+
+impl Default for SellerConfig {
+    fn default() -> Self {
+        Self {
+            country: "US".into(),
+            us_nexus: Vec::new(),
+        }
+    }
+}
+
+/// Rate table provider with EU/UK reverse charge and exemption handling.
+/// Uses headline national/state rates; local surtaxes need an external provider.
+pub struct BuiltinTaxProvider {
+    seller: SellerConfig,
+    vat: HashMap<&'static str, Decimal>,
+    us_saas: HashMap<&'static str, Decimal>,
+}
+
+const EU_COUNTRIES: &[&str] = &[
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU",
+    "IE", "IT", "LT", "LU", "LV", "MT", "NL", "PL", "PT", "RO", "SE", "SI", "SK",
+];
+
+impl BuiltinTaxProvider {
+    /// Create provider for a seller
+    pub fn new(seller: SellerConfig) -> Self {
+        let vat = HashMap::from([
+            // EU standard rates
+            ("AT", dec!(0.20)), ("BE", dec!(0.21)), ("BG", dec!(0.20)), ("CY", dec!(0.19)),
+            ("CZ", dec!(0.21)), ("DE", dec!(0.19)), ("DK", dec!(0.25)), ("EE", dec!(0.24)),
+            ("ES", dec!(0.21)), ("FI", dec!(0.255)), ("FR", dec!(0.20)), ("GR", dec!(0.24)),
+            ("HR", dec!(0.25)), ("HU", dec!(0.27)), ("IE", dec!(0.23)), ("IT", dec!(0.22)),
+            ("LT", dec!(0.21)), ("LU", dec!(0.17)), ("LV", dec!(0.21)), ("MT", dec!(0.18)),
+            ("NL", dec!(0.21)), ("PL", dec!(0.23)), ("PT", dec!(0.23)), ("RO", dec!(0.19)),
+            ("SE", dec!(0.25)), ("SI", dec!(0.22)), ("SK", dec!(0.23)),
+            // Other VAT/GST jurisdictions
+            ("GB", dec!(0.20)), ("NO", dec!(0.25)), ("CH", dec!(0.081)), ("AU", dec!(0.10)),
+            ("NZ", dec!(0.15)), ("SG", dec!(0.09)), ("IN", dec!(0.18)), ("JP", dec!(0.10)),
+            ("ZA", dec!(0.15)), ("AE", dec!(0.05)), ("SA", dec!(0.15)), ("NG", dec!(0.075)),
+            ("KE", dec!(0.16)),
+        ]);
+
+        // States taxing SaaS, state-level rate
+        let us_saas = HashMap::from([
+            ("AZ", dec!(0.056)), ("CT", dec!(0.01)), ("DC", dec!(0.06)), ("HI", dec!(0.04)),
+            ("IA", dec!(0.06)), ("KY", dec!(0.06)), ("MA", dec!(0.0625)), ("NM", dec!(0.04875)),
+            ("NY", dec!(0.04)), ("OH", dec!(0.0575)), ("PA", dec!(0.06)), ("RI", dec!(0.07)),
+            ("SC", dec!(0.06)), ("SD", dec!(0.042)), ("TN", dec!(0.07)), ("TX", dec!(0.05)),
+            ("UT", dec!(0.061)), ("WA", dec!(0.065)), ("WV", dec!(0.06)),
+        ]);
+
+        Self { seller, vat, us_saas }
+    }
+
+    /// Tax components for a jurisdiction: (jurisdiction, type, rate)
+    fn components(&self, address: &BillingAddress) -> Vec<(String, TaxType, Decimal)> {
+        let country = address.country.to_uppercase();
+        let region = address.region.as_deref().map(str::to_uppercase);
+
+        match country.as_str() {
+            "US" => {
+                let Some(state) = region else { return Vec::new() };
+                if !self.seller.us_nexus.iter().any(|s| s.eq_ignore_ascii_case(&state)) {
+                    return Vec::new();
+                }
+                self.us_saas.get(state.as_str())
+                    .map(|rate| vec![(format!("US-{}", state), TaxType::SalesTax, *rate)])
+                    .unwrap_or_default()
+            }
+            "CA" => {
+                let province = region.unwrap_or_default();
+                let gst = ("CA".to_string(), TaxType::Gst, dec!(0.05));
+                match province.as_str() {
+                    "ON" => vec![("CA-ON".into(), TaxType::Hst, dec!(0.13))],
+                    "NS" | "NB" | "NL" | "PE" => vec![(format!("CA-{}", province), TaxType::Hst, dec!(0.15))],
+                    "QC" => vec![gst, ("CA-QC".into(), TaxType::Qst, dec!(0.09975))],
+                    "BC" => vec![gst, ("CA-BC".into(), TaxType::Pst, dec!(0.07))],
+                    "MB" => vec![gst, ("CA-MB".into(), TaxType::Pst, dec!(0.07))],
+                    "SK" => vec![gst, ("CA-SK".into(), TaxType::Pst, dec!(0.06))],
+                    _ => vec![gst],
+                }
+            }
+            "JP" => vec![("JP".into(), TaxType::ConsumptionTax, dec!(0.10))],
+            "AU" | "NZ" | "SG" | "IN" => self.vat.get(country.as_str())
+                .map(|rate| vec![(country.clone(), TaxType::Gst, *rate)])
+                .unwrap_or_default(),
+            _ => self.vat.get(country.as_str())
+                .map(|rate| vec![(country.clone(), TaxType::Vat, *rate)])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// EU/UK B2B supply across borders with a VAT ID: buyer self-assesses
+    fn reverse_charge(&self, profile: &TaxProfile) -> bool {
+        let buyer = profile.address.country.to_uppercase();
+        let seller = self.seller.country.to_uppercase();
+        let valid_id = profile.tax_id.as_deref().is_some_and(|id| validate_vat_id(&buyer, id));
+
+        let in_scope = is_eu(&buyer) || buyer == "GB";
+        in_scope && profile.business && valid_id && buyer != seller
+    }
+}
+
+impl Default for BuiltinTaxProvider {
+    fn default() -> Self {
+        Self::new(SellerConfig::default())
+    }
+}
+
+impl TaxProvider for BuiltinTaxProvider {
+    fn name(&self) -> &str {
+        "builtin"
+    }
+
+    fn calculate(&self, request: &TaxRequest) -> Result<TaxBreakdown, TaxError> {
+        let profile = &request.profile;
+        if profile.address.country.len() != 2 {
+            return Err(TaxError::InvalidAddress(format!("country '{}'", profile.address.country)));
+        }
+
+        let reverse_charge = self.reverse_charge(profile);
+        let components = self.components(&profile.address);
+
+        let mut breakdown = TaxBreakdown {
+            reverse_charge,
+            customer_tax_id: profile.tax_id.clone(),
+            provider: self.name().into(),
+            ..Default::default()
+        };
+
+        for line in &request.lines {
+            if line.category == TaxCategory::NonTaxable || line.amount <= dec!(0) {
+                continue;
+            }
+
+            for (jurisdiction, tax_type, rate) in &components {
+                let exemption = profile.exemptions.iter().find(|c| c.covers(jurisdiction, request.date));
+                let exempt_reason = if reverse_charge {
+                    Some("Reverse charge".to_string())
+                } else {
+                    exemption.map(|c| format!("Exemption certificate {} ({})", c.certificate_number, c.reason))
+                };
+
+                let (rate, tax_amount) = if exempt_reason.is_some() {
+                    (dec!(0), dec!(0))
+                } else {
+                    (*rate, (line.amount * rate).round_dp(2))
+                };
+
+                breakdown.lines.push(LineTax {
+                    index: line.index,
+                    jurisdiction: jurisdiction.clone(),
+                    tax_type: *tax_type,
+                    rate,
+                    taxable_amount: line.amount,
+                    tax_amount,
+                    exempt_reason,
+                });
+            }
+        }
+
+        if reverse_charge {
+            breakdown.notes.push(if profile.address.country.eq_ignore_ascii_case("GB") {
+                "Reverse charge: customer to account for VAT to HMRC".to_string()
+            } else {
+                "Reverse charge: VAT to be accounted for by the recipient (Article 196, Directive 2006/112/EC)".to_string()
+            });
+        }
+        if components.is_empty() {
+            breakdown.notes.push(format!("No tax collected for {}", jurisdiction_label(&profile.address)));
+        }
+
+        breakdown.summarize();
+        Ok(breakdown)
+    }
+}
+
+fn is_eu(country: &str) -> bool {
+    EU_COUNTRIES.contains(&country)
+}
+
+fn jurisdiction_label(address: &BillingAddress) -> String {
+    match &address.region {
+        Some(region) => format!("{}-{}", address.country.to_uppercase(), region.to_uppercase()),
+        None => address.country.to_uppercase(),
+    }
+}
+
+/// Format check for a VAT ID (country prefix + 2..=12 alphanumerics).
+/// Registry validation (VIES/HMRC) belongs to an external provider.
+pub fn validate_vat_id(country: &str, vat_id: &str) -> bool {
+    let id: String = vat_id.chars().filter(|c| !c.is_whitespace() && *c != '-' && *c != '.').collect();
+    let id = id.to_uppercase();
+    // Greece uses EL as its VAT prefix
+    let prefix = if country.eq_ignore_ascii_case("GR") { "EL".to_string() } else { country.to_uppercase() };
+
+    id.strip_prefix(&prefix)
+        .is_some_and(|rest| (2..=12).contains(&rest.len()) && rest.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+// =============================================================================
+// Service
+// =============================================================================
+
+/// Tenant tax profiles plus the active provider
+pub struct TaxService {
+    provider: Arc<dyn TaxProvider>,
+    profiles: Arc<RwLock<HashMap<Uuid, TaxProfile>>>,
+}
+
+impl TaxService {
+    /// Create service with the built-in rate table
+    pub fn new() -> Self {
+        Self {
+            provider: Arc::new(BuiltinTaxProvider::default()),
+            profiles: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Use a different tax provider
+    pub fn with_provider(mut self, provider: Arc<dyn TaxProvider>) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    /// Set a tenant's address and registration
+    pub fn set_profile(&self, profile: TaxProfile) {
+        self.profiles.write().insert(profile.tenant_id, profile);
+    }
+
+    /// Get a tenant's profile
+    pub fn get_profile(&self, tenant_id: Uuid) -> Option<TaxProfile> {
+        self.profiles.read().get(&tenant_id).cloned()
+    }
+
+    /// File an exemption certificate for a tenant
+    pub fn add_exemption(&self, tenant_id: Uuid, certificate: ExemptionCertificate) -> Result<(), TaxError> {
+        let mut profiles = self.profiles.write();
+        let profile = profiles.get_mut(&tenant_id).ok_or(TaxError::NoProfile)?;
+        profile.exemptions.push(certificate);
+        Ok(())
+    }
+
+    /// Compute taxes for a tenant's invoice lines
+    pub fn calculate(&self, tenant_id: Uuid, date: NaiveDate, currency: &str, lines: Vec<TaxableLine>) -> Result<TaxBreakdown, TaxError> {
+        let profile = self.get_profile(tenant_id).ok_or(TaxError::NoProfile)?;
+        let request = TaxRequest {
+            profile,
+            date,
+            currency: currency.into(),
+            lines,
+        };
+        self.provider.calculate(&request)
+    }
+}
+
+impl Default for TaxService {
+    fn default() -> Self { Self::new() }
+}
+
+/// Tax error
+#[derive(Debug, Clone)]
+pub enum TaxError {
+    /// Tenant has no billing address on file
+    NoProfile,
+    /// Address cannot be located
+    InvalidAddress(String),
+    /// External provider failed
+    Provider(String),
+}
+
+impl std::fmt::Display for TaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoProfile => write!(f, "No tax profile for tenant"),
+            Self::InvalidAddress(e) => write!(f, "Invalid billing address: {}", e),
+            Self::Provider(e) => write!(f, "Tax provider error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TaxError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn profile(country: &str, region: Option<&str>) -> TaxProfile {
+        TaxProfile {
+            tenant_id: Uuid::new_v4(),
+            address: BillingAddress {
+                line1: "1 Main Street".into(),
+                city: "Springfield".into(),
+                region: region.map(Into::into),
+                postal_code: "12345".into(),
+                country: country.into(),
+            },
+            tax_id: None,
+            business: false,
+            exemptions: Vec::new(),
+        }
+    }
+
+    fn certificate(jurisdiction: &str, valid_from: NaiveDate, valid_until: Option<NaiveDate>) -> ExemptionCertificate {
+        ExemptionCertificate {
+            id: Uuid::new_v4(),
+            jurisdiction: jurisdiction.into(),
+            certificate_number: "EX-1".into(),
+            reason: "resale".into(),
+            valid_from,
+            valid_until,
+        }
+    }
+
+    fn line(index: usize, amount: Decimal, category: TaxCategory) -> TaxableLine {
+        TaxableLine { index, description: format!("Line {}", index), amount, category }
+    }
+
+    fn request(profile: TaxProfile, date: NaiveDate, lines: Vec<TaxableLine>) -> TaxRequest {
+        TaxRequest { profile, date, currency: "USD".into(), lines }
+    }
+
+    fn seller(country: &str, us_nexus: &[&str]) -> BuiltinTaxProvider {
+        BuiltinTaxProvider::new(SellerConfig {
+            country: country.into(),
+            us_nexus: us_nexus.iter().map(|s| s.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_exemption_validity_is_inclusive() {
+        let cert = certificate("US", date(2024, 1, 1), Some(date(2024, 12, 31)));
+        assert!(!cert.covers("US", date(2023, 12, 31)));
+        assert!(cert.covers("US", date(2024, 1, 1)));
+        assert!(cert.covers("US", date(2024, 12, 31)));
+        assert!(!cert.covers("US", date(2025, 1, 1)));
+
+        let open_ended = certificate("US", date(2024, 1, 1), None);
+        assert!(open_ended.covers("US", date(2099, 1, 1)));
+    }
+
+    #[test]
+    fn test_exemption_jurisdiction_prefix() {
+        let country = certificate("US", date(2024, 1, 1), None);
+        assert!(country.covers("US-NY", date(2024, 6, 1)));
+
+        let state = certificate("US-NY", date(2024, 1, 1), None);
+        assert!(state.covers("US-NY", date(2024, 6, 1)));
+        assert!(!state.covers("US-TX", date(2024, 6, 1)));
+        assert!(!state.covers("US", date(2024, 6, 1)));
+        // A prefix of the code is not a parent jurisdiction
+        assert!(!certificate("CA", date(2024, 1, 1), None).covers("CAN", date(2024, 6, 1)));
+    }
+
+    #[test]
+    fn test_exemption_applies_on_its_last_day_only() {
+        let provider = seller("US", &["NY"]);
+        let mut buyer = profile("US", Some("NY"));
+        buyer.exemptions.push(certificate("US-NY", date(2024, 1, 1), Some(date(2024, 3, 31))));
+        let lines = || vec![line(0, dec!(100), TaxCategory::DigitalService)];
+
+        let covered = provider.calculate(&request(buyer.clone(), date(2024, 3, 31), lines())).unwrap();
+        assert_eq!(covered.total_tax, dec!(0));
+        assert_eq!(covered.lines[0].rate, dec!(0));
+        assert_eq!(covered.lines[0].exempt_reason.as_deref(), Some("Exemption certificate EX-1 (resale)"));
+
+        let lapsed = provider.calculate(&request(buyer, date(2024, 4, 1), lines())).unwrap();
+        assert_eq!(lapsed.total_tax, dec!(4.00));
+        assert!(lapsed.lines[0].exempt_reason.is_none());
+    }
+
+    #[test]
+    fn test_vat_per_line_and_summary() {
+        let provider = seller("US", &[]);
+        let breakdown = provider.calculate(&request(profile("DE", None), date(2024, 6, 1), vec![
+            line(0, dec!(99), TaxCategory::DigitalService),
+            line(1, dec!(10.55), TaxCategory::DigitalService),
+        ])).unwrap();
+
+        assert_eq!(breakdown.line_tax(0), dec!(18.81));
+        assert_eq!(breakdown.line_tax(1), dec!(2.00));
+        assert_eq!(breakdown.total_tax, dec!(20.81));
+        assert_eq!(breakdown.summary.len(), 1);
+        assert_eq!(breakdown.summary[0].taxable_amount, dec!(109.55));
+        assert_eq!(breakdown.summary[0].tax_type, TaxType::Vat);
+        assert!(!breakdown.reverse_charge);
+    }
+
+    #[test]
+    fn test_non_taxable_and_credit_lines_are_skipped() {
+        let provider = seller("US", &[]);
+        let breakdown = provider.calculate(&request(profile("GB", None), date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+            line(1, dec!(-40), TaxCategory::DigitalService),
+            line(2, dec!(0), TaxCategory::DigitalService),
+            line(3, dec!(50), TaxCategory::NonTaxable),
+        ])).unwrap();
+
+        assert_eq!(breakdown.lines.len(), 1);
+        assert_eq!(breakdown.lines[0].index, 0);
+        assert_eq!(breakdown.total_tax, dec!(20.00));
+    }
+
+    #[test]
+    fn test_reverse_charge() {
+        let provider = seller("US", &[]);
+        let mut buyer = profile("FR", None);
+        buyer.business = true;
+        buyer.tax_id = Some("FR 12 345678901".into());
+
+        let breakdown = provider.calculate(&request(buyer.clone(), date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+        ])).unwrap();
+        assert!(breakdown.reverse_charge);
+        assert_eq!(breakdown.total_tax, dec!(0));
+        assert_eq!(breakdown.lines[0].exempt_reason.as_deref(), Some("Reverse charge"));
+        assert!(breakdown.notes[0].contains("Article 196"));
+
+        // Same country as the seller, consumer, or malformed ID: VAT charged
+        let domestic = seller("FR", &[]).calculate(&request(buyer.clone(), date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+        ])).unwrap();
+        assert_eq!(domestic.total_tax, dec!(20.00));
+
+        let mut consumer = buyer.clone();
+        consumer.business = false;
+        let consumer = provider.calculate(&request(consumer, date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+        ])).unwrap();
+        assert!(!consumer.reverse_charge);
+
+        buyer.tax_id = Some("DE123456789".into());
+        let wrong_country = provider.calculate(&request(buyer, date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+        ])).unwrap();
+        assert!(!wrong_country.reverse_charge);
+    }
+
+    #[test]
+    fn test_canadian_gst_and_pst() {
+        let provider = seller("US", &[]);
+        let breakdown = provider.calculate(&request(profile("CA", Some("bc")), date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+        ])).unwrap();
+
+        assert_eq!(breakdown.lines.len(), 2);
+        assert_eq!(breakdown.line_tax(0), dec!(12.00));
+        let types: Vec<TaxType> = breakdown.summary.iter().map(|s| s.tax_type).collect();
+        assert_eq!(types, vec![TaxType::Gst, TaxType::Pst]);
+
+        let ontario = provider.calculate(&request(profile("CA", Some("ON")), date(2024, 6, 1), vec![
+            line(0, dec!(100), TaxCategory::DigitalService),
+        ])).unwrap();
+        assert_eq!(ontario.lines.len(), 1);
+        assert_eq!(ontario.lines[0].tax_type, TaxType::Hst);
+        assert_eq!(ontario.total_tax, dec!(13.00));
+    }
+
+    #[test]
+    fn test_us_sales_tax_requires_nexus() {
+        let lines = || vec![line(0, dec!(100), TaxCategory::DigitalService)];
+
+        let no_nexus = seller("US", &["TX"]).calculate(&request(profile("US", Some("NY")), date(2024, 6, 1), lines())).unwrap();
+        assert!(no_nexus.lines.is_empty());
+        assert_eq!(no_nexus.notes, vec!["No tax collected for US-NY".to_string()]);
+
+        let nexus = seller("US", &["ny"]).calculate(&request(profile("US", Some("NY")), date(2024, 6, 1), lines())).unwrap();
+        assert_eq!(nexus.lines[0].jurisdiction, "US-NY");
+        assert_eq!(nexus.total_tax, dec!(4.00));
+
+        // Nexus in a state that does not tax SaaS
+        let untaxed = seller("US", &["CA"]).calculate(&request(profile("US", Some("CA")), date(2024, 6, 1), lines())).unwrap();
+        assert_eq!(untaxed.total_tax, dec!(0));
+    }
+
+    #[test]
+    fn test_invalid_country() {
+        let result = BuiltinTaxProvider::default().calculate(&request(profile("USA", None), date(2024, 6, 1), Vec::new()));
+        assert!(matches!(result, Err(TaxError::InvalidAddress(_))));
+    }
+
+    #[test]
+    fn test_validate_vat_id() {
+        assert!(validate_vat_id("DE", "DE123456789"));
+        assert!(validate_vat_id("de", "de 123-456.789"));
+        assert!(validate_vat_id("GR", "EL123456789"));
+        assert!(!validate_vat_id("GR", "GR123456789"));
+        assert!(!validate_vat_id("DE", "FR123456789"));
+        assert!(!validate_vat_id("DE", "DE1"));
+        assert!(!validate_vat_id("DE", "DE1234567890123"));
+        assert!(!validate_vat_id("DE", "DE12345_789"));
+    }
+
+    #[test]
+    fn test_service_requires_profile() {
+        let service = TaxService::new();
+        let tenant_id = Uuid::new_v4();
+        assert!(matches!(service.calculate(tenant_id, date(2024, 6, 1), "USD", Vec::new()), Err(TaxError::NoProfile)));
+        assert!(matches!(
+            service.add_exemption(tenant_id, certificate("US", date(2024, 1, 1), None)),
+            Err(TaxError::NoProfile)
+        ));
+    }
+}
@@ -57,6 +57,8 @@ pub struct TunnelConfigResponse {
     pub mtu: u16,
     pub keepalive: u16,
     pub policies: Vec<crate::policy::Policy>,
+    #[serde(default)]
+    pub split_tunnel: Option<crate::split_tunnel::SplitTunnelConfig>,
 }
 
 impl AuthManager {
@@ -150,9 +152,53 @@ impl AuthManager {
             mtu: config.mtu,
             keepalive: config.keepalive,
             policies: config.policies,
+            split_tunnel: config.split_tunnel,
         })
     }
     
+    /// Fetch the PoPs this device may connect to
+    pub async fn get_pop_catalog(&self, token: &str) -> Result<crate::gateway::PopCatalog, AuthError> {
+        let response = self.client
+            .get(&format!("{}/api/v1/device/pops", self.server_url))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| AuthError::NetworkError(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(AuthError::ConfigError(format!(
+                "Server returned {}",
+                response.status()
+            )));
+        }
+        
+        response.json().await
+            .map_err(|e| AuthError::ParseError(e.to_string()))
+    }
+    
+    /// Report split tunnel rule counters to the controller
+    pub async fn report_split_tunnel_telemetry(
+        &self,
+        token: &str,
+        telemetry: &crate::split_tunnel::SplitTunnelTelemetry,
+    ) -> Result<(), AuthError> {
+        let response = self.client
+            .post(&format!("{}/api/v1/device/telemetry/split-tunnel", self.server_url))
+            .bearer_auth(token)
+            .json(telemetry)
+            .send()
+            .await
+            .map_err(|e| AuthError::NetworkError(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(AuthError::ConfigError(format!(
+                "Server returned {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+    
     pub async fn refresh_token(&self) -> Result<(), AuthError> {
         let current_token = self.token.read().clone()
             .ok_or(AuthError::NoToken)?;
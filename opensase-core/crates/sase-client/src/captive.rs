@@ -0,0 +1,241 @@
+//! Captive Portal Detection
+//!
+//! Probes well-known connectivity-check endpoints over plain HTTP. A
+//! captive network intercepts them with a redirect or its own login page,
+//! which is how it is told apart from an open network or no network.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A connectivity check whose answer on an open network is known
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaptiveProbe {
+    pub url: String,
+    pub expected_status: u16,
+    /// Body must contain this, when set
+    #[serde(default)]
+    pub expected_body: Option<String>,
+}
+
+impl CaptiveProbe {
+    /// Probes of the major OS vendors, which portals commonly special-case
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            CaptiveProbe {
+                url: "http://connectivitycheck.gstatic.com/generate_204".to_string(),
+                expected_status: 204,
+                expected_body: None,
+            },
+            CaptiveProbe {
+                url: "http://captive.apple.com/hotspot-detect.html".to_string(),
+                expected_status: 200,
+                expected_body: Some("Success".to_string()),
+            },
+            CaptiveProbe {
+                url: "http://www.msftconnecttest.com/connecttest.txt".to_string(),
+                expected_status: 200,
+                expected_body: Some("Microsoft Connect Test".to_string()),
+            },
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum PortalStatus {
+    /// Probes answered as expected
+    Open,
+    /// Probes were intercepted
+    Captive { portal_url: Option<String> },
+    /// No probe got an answer
+    Offline,
+}
+
+pub struct CaptivePortalDetector {
+    client: reqwest::Client,
+    probes: Vec<CaptiveProbe>,
+}
+
+impl CaptivePortalDetector {
+    pub fn new() -> Self {
+        Self::with_probes(CaptiveProbe::defaults())
+    }
+
+    pub fn with_probes(probes: Vec<CaptiveProbe>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(3))
+            // The redirect itself is the portal signal
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self { client, probes }
+    }
+
+    /// Probe until one answer is conclusive
+    ///
+    /// Any expected answer means the network is open: portals sometimes
+    /// whitelist one vendor's endpoint, never all of them.
+    pub async fn detect(&self) -> PortalStatus {
+        let mut captive: Option<PortalStatus> = None;
+
+        for probe in &self.probes {
+            let response = match self.client.get(&probe.url).send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::debug!("Captive probe {} failed: {}", probe.url, e);
+                    continue;
+                }
+            };
+
+            let status = response.status();
+            let location = response.headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .map(|l| l.to_string());
+            let expected = status.as_u16() == probe.expected_status
+                && match &probe.expected_body {
+                    Some(body) => response.text().await.map(|t| t.contains(body.as_str())).unwrap_or(false),
+                    None => true,
+                };
+
+            if expected {
+                return PortalStatus::Open;
+            }
+            tracing::info!("Captive probe {} intercepted ({})", probe.url, status);
+            if captive.is_none() || location.is_some() {
+                captive = Some(PortalStatus::Captive { portal_url: location.or_else(|| Some(probe.url.clone())) });
+            }
+        }
+
+        captive.unwrap_or(PortalStatus::Offline)
+    }
+}
+
+impl Default for CaptivePortalDetector {
+    fn default() -> Self { Self::new() }
+}
+
+/// Time-boxed window in which traffic bypasses the tunnel so the user can
+/// sign in to a captive portal
+#[derive(Clone, Copy, Debug)]
+pub struct PassThroughWindow {
+    pub started_at: Instant,
+    pub expires_at: Instant,
+}
+
+impl PassThroughWindow {
+    pub fn new(duration: Duration) -> Self {
+        let started_at = Instant::now();
+        Self { started_at, expires_at: started_at + duration }
+    }
+
+    /// Countdown until the tunnel is forced back on
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answers each path with a canned response, like a portal or an open network would
+    async fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0u8; 4096];
+                let n = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let response = responses.iter()
+                    .find(|(p, _)| *p == path)
+                    .map(|(_, r)| *r)
+                    .unwrap_or("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        base
+    }
+
+    fn probe(base: &str, path: &str, expected_status: u16, expected_body: Option<&str>) -> CaptiveProbe {
+        CaptiveProbe {
+            url: format!("{}{}", base, path),
+            expected_status,
+            expected_body: expected_body.map(|b| b.to_string()),
+        }
+    }
+
+    const NO_CONTENT: &str = "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n";
+    const SUCCESS: &str = "HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nSuccess";
+    const LOGIN_PAGE: &str = "HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nPlease log in";
+    const REDIRECT: &str = "HTTP/1.1 302 Found\r\nLocation: http://portal.hotel.example/login\r\nContent-Length: 0\r\n\r\n";
+
+    #[tokio::test]
+    async fn test_expected_answers_mean_open() {
+        let base = serve(vec![("/generate_204", NO_CONTENT), ("/hotspot", SUCCESS)]).await;
+        let detector = CaptivePortalDetector::with_probes(vec![
+            probe(&base, "/generate_204", 204, None),
+            probe(&base, "/hotspot", 200, Some("Success")),
+        ]);
+        assert_eq!(detector.detect().await, PortalStatus::Open);
+
+        // One whitelisted endpoint is enough
+        let base = serve(vec![("/generate_204", REDIRECT), ("/hotspot", SUCCESS)]).await;
+        let detector = CaptivePortalDetector::with_probes(vec![
+            probe(&base, "/generate_204", 204, None),
+            probe(&base, "/hotspot", 200, Some("Success")),
+        ]);
+        assert_eq!(detector.detect().await, PortalStatus::Open);
+    }
+
+    #[tokio::test]
+    async fn test_intercepted_probes_mean_captive() {
+        // A redirect names the portal even when an earlier probe saw a login page
+        let base = serve(vec![("/hotspot", LOGIN_PAGE), ("/generate_204", REDIRECT)]).await;
+        let detector = CaptivePortalDetector::with_probes(vec![
+            probe(&base, "/hotspot", 200, Some("Success")),
+            probe(&base, "/generate_204", 204, None),
+        ]);
+        assert_eq!(
+            detector.detect().await,
+            PortalStatus::Captive { portal_url: Some("http://portal.hotel.example/login".to_string()) }
+        );
+
+        // Without a redirect the intercepted probe is the best guess
+        let base = serve(vec![("/hotspot", LOGIN_PAGE)]).await;
+        let detector = CaptivePortalDetector::with_probes(vec![probe(&base, "/hotspot", 200, Some("Success"))]);
+        assert_eq!(
+            detector.detect().await,
+            PortalStatus::Captive { portal_url: Some(format!("{}/hotspot", base)) }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_answers_mean_offline() {
+        // Nothing listens on a port that was just released
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let detector = CaptivePortalDetector::with_probes(vec![probe(&base, "/generate_204", 204, None)]);
+        assert_eq!(detector.detect().await, PortalStatus::Offline);
+    }
+
+    #[test]
+    fn test_pass_through_window() {
+        let window = PassThroughWindow::new(Duration::from_secs(300));
+        assert!(!window.expired());
+        assert!(window.remaining() <= Duration::from_secs(300));
+        assert!(window.remaining() > Duration::from_secs(299));
+
+        let window = PassThroughWindow::new(Duration::ZERO);
+        assert!(window.expired());
+        assert_eq!(window.remaining(), Duration::ZERO);
+    }
+}
@@ -33,6 +33,9 @@ pub struct ConnectionConfig {
     pub max_reconnect_attempts: u32,
     pub keepalive_interval_secs: u64,
     pub mtu: u16,
+    /// PoP probing, failover and preferred PoP
+    #[serde(default)]
+    pub pop_selection: crate::gateway::PopSelectionPolicy,
 }
 
 impl Default for ConnectionConfig {
@@ -44,6 +47,7 @@ impl Default for ConnectionConfig {
             max_reconnect_attempts: 10,
             keepalive_interval_secs: 25,
             mtu: 1420,
+            pop_selection: Default::default(),
         }
     }
 }
@@ -55,6 +59,9 @@ pub struct FeatureConfig {
     pub dns_protection: bool,
     pub posture_check: bool,
     pub kill_switch: bool,
+    /// Local split tunnel rules, used until the controller pushes its own
+    #[serde(default)]
+    pub split_tunnel_rules: crate::split_tunnel::SplitTunnelConfig,
 }
 
 impl Default for FeatureConfig {
@@ -65,6 +72,7 @@ impl Default for FeatureConfig {
             dns_protection: true,
             posture_check: true,
             kill_switch: false,
+            split_tunnel_rules: Default::default(),
         }
     }
 }
@@ -2,11 +2,14 @@
 //!
 //! Secure DNS configuration and protection.
 
+use crate::split_tunnel::DnsPlan;
 use std::net::IpAddr;
 
 pub struct DnsManager {
     original_servers: parking_lot::RwLock<Vec<String>>,
     configured: parking_lot::RwLock<bool>,
+    /// Per-domain resolver routing applied for split tunneling
+    split_plan: parking_lot::RwLock<Option<DnsPlan>>,
 }
 
 impl DnsManager {
@@ -14,6 +17,7 @@ impl DnsManager {
         Self {
             original_servers: parking_lot::RwLock::new(Vec::new()),
             configured: parking_lot::RwLock::new(false),
+            split_plan: parking_lot::RwLock::new(None),
         }
     }
     
@@ -37,7 +41,58 @@ impl DnsManager {
         Ok(())
     }
     
+    /// Route names to tunnel or local resolvers per the split tunnel rules
+    ///
+    /// Tunneled domains resolve through `tunnel_servers`, excluded ones
+    /// through the resolvers that were active before connecting. Replaces
+    /// any previously applied plan.
+    pub async fn configure_split(&self, tunnel_servers: &[String], plan: &DnsPlan) -> Result<(), crate::ClientError> {
+        if self.split_plan.read().as_ref() == Some(plan) {
+            return Ok(());
+        }
+        self.restore_split().await?;
+        
+        let local_servers = self.original_servers.read().clone();
+        tracing::info!(
+            "Configuring split DNS: {} tunneled, {} local domains",
+            plan.tunnel_domains.len(), plan.local_domains.len()
+        );
+        
+        #[cfg(target_os = "windows")]
+        self.configure_split_windows(tunnel_servers, &local_servers, plan).await?;
+        
+        #[cfg(target_os = "macos")]
+        self.configure_split_macos(tunnel_servers, &local_servers, plan).await?;
+        
+        #[cfg(target_os = "linux")]
+        self.configure_split_linux(tunnel_servers, &local_servers, plan).await?;
+        
+        *self.split_plan.write() = Some(plan.clone());
+        Ok(())
+    }
+    
+    /// Remove per-domain resolver routing
+    pub async fn restore_split(&self) -> Result<(), crate::ClientError> {
+        let Some(plan) = self.split_plan.write().take() else {
+            return Ok(());
+        };
+        
+        #[cfg(target_os = "windows")]
+        self.restore_split_windows().await?;
+        
+        #[cfg(target_os = "macos")]
+        self.restore_split_macos(&plan).await?;
+        
+        #[cfg(target_os = "linux")]
+        self.restore_split_linux().await?;
+        
+        tracing::debug!("Removed split DNS for {} domains", plan.tunnel_domains.len() + plan.local_domains.len());
+        Ok(())
+    }
+    
     pub async fn restore(&self) -> Result<(), crate::ClientError> {
+        self.restore_split().await?;
+        
         if !*self.configured.read() {
             return Ok(());
         }
@@ -187,8 +242,159 @@ quit"#,
         }
         Ok(())
     }
+    
+    #[cfg(target_os = "windows")]
+    async fn configure_split_windows(
+        &self,
+        tunnel_servers: &[String],
+        local_servers: &[String],
+        plan: &DnsPlan,
+    ) -> Result<(), crate::ClientError> {
+        // NRPT rules, tagged so they can be found again on restore
+        let rules = plan.tunnel_domains.iter().map(|d| (d, tunnel_servers))
+            .chain(plan.local_domains.iter().map(|d| (d, local_servers)))
+            .filter(|(_, servers)| !servers.is_empty());
+        for (domain, servers) in rules {
+            let command = format!(
+                "Add-DnsClientNrptRule -Namespace '.{}' -NameServers {} -Comment '{}'",
+                domain,
+                servers.iter().map(|s| format!("'{}'", s)).collect::<Vec<_>>().join(","),
+                NRPT_COMMENT
+            );
+            let output = tokio::process::Command::new("powershell")
+                .args(["-NoProfile", "-Command", &command])
+                .output()
+                .await
+                .map_err(|e| crate::ClientError::DnsFailed(e.to_string()))?;
+            
+            if !output.status.success() {
+                tracing::warn!("Failed to add NRPT rule for {}: {:?}", domain, String::from_utf8_lossy(&output.stderr));
+            }
+        }
+        Ok(())
+    }
+    
+    #[cfg(target_os = "windows")]
+    async fn restore_split_windows(&self) -> Result<(), crate::ClientError> {
+        let command = format!(
+            "Get-DnsClientNrptRule | Where-Object Comment -eq '{}' | Remove-DnsClientNrptRule -Force",
+            NRPT_COMMENT
+        );
+        let _ = tokio::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &command])
+            .output()
+            .await;
+        Ok(())
+    }
+    
+    #[cfg(target_os = "macos")]
+    async fn configure_split_macos(
+        &self,
+        tunnel_servers: &[String],
+        local_servers: &[String],
+        plan: &DnsPlan,
+    ) -> Result<(), crate::ClientError> {
+        // One /etc/resolver file per domain overrides the default resolvers
+        std::fs::create_dir_all(MACOS_RESOLVER_DIR)
+            .map_err(|e| crate::ClientError::DnsFailed(e.to_string()))?;
+        let files = plan.tunnel_domains.iter().map(|d| (d, tunnel_servers))
+            .chain(plan.local_domains.iter().map(|d| (d, local_servers)))
+            .filter(|(_, servers)| !servers.is_empty());
+        for (domain, servers) in files {
+            let content = format!(
+                "# {}\n{}\n",
+                MACOS_RESOLVER_MARKER,
+                servers.iter().map(|s| format!("nameserver {}", s)).collect::<Vec<_>>().join("\n")
+            );
+            std::fs::write(format!("{}/{}", MACOS_RESOLVER_DIR, domain), content)
+                .map_err(|e| crate::ClientError::DnsFailed(e.to_string()))?;
+        }
+        Ok(())
+    }
+    
+    #[cfg(target_os = "macos")]
+    async fn restore_split_macos(&self, plan: &DnsPlan) -> Result<(), crate::ClientError> {
+        for domain in plan.tunnel_domains.iter().chain(&plan.local_domains) {
+            let path = format!("{}/{}", MACOS_RESOLVER_DIR, domain);
+            // Leave resolver files we didn't write alone
+            let ours = std::fs::read_to_string(&path)
+                .map(|c| c.contains(MACOS_RESOLVER_MARKER))
+                .unwrap_or(false);
+            if ours {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        Ok(())
+    }
+    
+    #[cfg(target_os = "linux")]
+    async fn configure_split_linux(
+        &self,
+        _tunnel_servers: &[String],
+        _local_servers: &[String],
+        plan: &DnsPlan,
+    ) -> Result<(), crate::ClientError> {
+        if !std::path::Path::new("/run/systemd/resolve/resolv.conf").exists() {
+            tracing::warn!("Per-domain DNS routing needs systemd-resolved; split DNS not applied");
+            return Ok(());
+        }
+        
+        // Routing domains send matching names to the link's resolvers;
+        // "~." makes the tunnel the default for everything else
+        let mut tunnel_domains: Vec<String> = plan.tunnel_domains.iter().map(|d| format!("~{}", d)).collect();
+        if plan.default_tunnel {
+            tunnel_domains.push("~.".to_string());
+        }
+        let mut args = vec!["domain".to_string(), crate::split_tunnel::TUNNEL_INTERFACE.to_string()];
+        args.extend(tunnel_domains);
+        let _ = tokio::process::Command::new("resolvectl")
+            .args(&args)
+            .output()
+            .await;
+        let _ = tokio::process::Command::new("resolvectl")
+            .args(["default-route", crate::split_tunnel::TUNNEL_INTERFACE, if plan.default_tunnel { "yes" } else { "no" }])
+            .output()
+            .await;
+        
+        if !plan.local_domains.is_empty() {
+            let gateway = crate::split_tunnel::default_gateway().await
+                .map_err(|e| crate::ClientError::DnsFailed(e.to_string()))?;
+            let mut args = vec!["domain".to_string(), gateway.interface];
+            args.extend(plan.local_domains.iter().map(|d| format!("~{}", d)));
+            let _ = tokio::process::Command::new("resolvectl")
+                .args(&args)
+                .output()
+                .await;
+        }
+        Ok(())
+    }
+    
+    #[cfg(target_os = "linux")]
+    async fn restore_split_linux(&self) -> Result<(), crate::ClientError> {
+        let _ = tokio::process::Command::new("resolvectl")
+            .args(["domain", crate::split_tunnel::TUNNEL_INTERFACE, ""])
+            .output()
+            .await;
+        if let Ok(gateway) = crate::split_tunnel::default_gateway().await {
+            // Hands the physical link back to its network manager's settings
+            let _ = tokio::process::Command::new("resolvectl")
+                .args(["revert", &gateway.interface])
+                .output()
+                .await;
+        }
+        Ok(())
+    }
 }
 
+#[cfg(target_os = "windows")]
+const NRPT_COMMENT: &str = "OpenSASE split tunnel";
+
+#[cfg(target_os = "macos")]
+const MACOS_RESOLVER_DIR: &str = "/etc/resolver";
+
+#[cfg(target_os = "macos")]
+const MACOS_RESOLVER_MARKER: &str = "Managed by OpenSASE";
+
 impl Default for DnsManager {
     fn default() -> Self { Self::new() }
 }
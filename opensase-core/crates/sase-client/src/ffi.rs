@@ -109,6 +109,7 @@ pub extern "C" fn oscs_get_state(client: *const crate::SaseClient) -> i32 {
         crate::ClientState::Connected => 4,
         crate::ClientState::Reconnecting => 5,
         crate::ClientState::Error => 6,
+        crate::ClientState::PassThrough => 7,
     }
 }
 
@@ -1,13 +1,27 @@
 //! Gateway Selection and Latency Probing
 //!
 //! Optimal gateway selection based on latency and availability.
+//!
+//! Gateways are the PoPs of the controller's catalog. The nearest
+//! candidates are probed for RTT and loss, and the client sticks to the PoP
+//! it is on until that PoP degrades or another one is clearly better, so
+//! sessions pinned to a PoP are not moved for a few milliseconds.
 
-use std::net::SocketAddr;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::{Duration, Instant};
 
 pub struct GatewaySelector {
     gateways: parking_lot::RwLock<Vec<GatewayEndpoint>>,
     probe_results: parking_lot::RwLock<Vec<ProbeResult>>,
+    policy: parking_lot::RwLock<PopSelectionPolicy>,
+    /// Where the controller places the client, for picking candidates
+    client_location: parking_lot::RwLock<Option<GeoLocation>>,
+    /// PoP the tunnel is on
+    current: parking_lot::RwLock<Option<String>>,
+    /// Consecutive health checks the current PoP failed
+    degraded_checks: AtomicU32,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -21,6 +35,17 @@ pub struct GatewayEndpoint {
     pub capacity: GatewayCapacity,
 }
 
+impl GatewayEndpoint {
+    /// WireGuard endpoint in `host:port` form
+    pub fn endpoint(&self) -> String {
+        if self.host.contains(':') && !self.host.starts_with('[') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct GeoLocation {
     pub city: String,
@@ -29,6 +54,18 @@ pub struct GeoLocation {
     pub longitude: f64,
 }
 
+impl GeoLocation {
+    /// Great-circle distance in kilometres
+    pub fn distance_km(&self, other: &GeoLocation) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct GatewayCapacity {
     pub max_connections: u32,
@@ -36,27 +73,311 @@ pub struct GatewayCapacity {
     pub bandwidth_mbps: u32,
 }
 
-#[derive(Clone, Debug)]
+impl GatewayCapacity {
+    /// Load in percent of connection capacity
+    pub fn load_percent(&self) -> u32 {
+        (self.current_connections.saturating_mul(100)) / self.max_connections.max(1)
+    }
+}
+
+/// PoP catalog as served by the controller
+#[derive(Clone, Debug, Deserialize)]
+pub struct PopCatalog {
+    pub pops: Vec<GatewayEndpoint>,
+    /// Client location from the controller's geo-IP lookup
+    #[serde(default)]
+    pub client_location: Option<GeoLocation>,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct ProbeResult {
     pub gateway_id: String,
+    /// Median RTT of the probes that were answered
     pub latency_ms: Option<u32>,
+    pub loss_percent: f32,
     pub success: bool,
     pub probed_at: chrono::DateTime<chrono::Utc>,
 }
 
+// =============================================================================
+// Selection Policy
+// =============================================================================
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PopSelectionPolicy {
+    /// PoPs probed per selection, nearest first
+    pub candidates: usize,
+    pub probes_per_pop: u32,
+    pub probe_timeout_ms: u64,
+    /// Latency each percent of loss is scored as
+    pub loss_penalty_ms: u32,
+    /// Another PoP must score this much better to move a connected client
+    pub switch_margin_ms: u32,
+    /// Latency above which the connected PoP counts as degraded
+    pub max_latency_ms: u32,
+    /// Loss above which the connected PoP counts as degraded
+    pub max_loss_percent: f32,
+    /// Consecutive degraded health checks before failing over
+    pub failover_after: u32,
+    pub health_interval_secs: u64,
+    #[serde(default)]
+    pub preferred: Option<PreferredPop>,
+}
+
+impl Default for PopSelectionPolicy {
+    fn default() -> Self {
+        Self {
+            candidates: 5,
+            probes_per_pop: 4,
+            probe_timeout_ms: 2000,
+            loss_penalty_ms: 10,
+            switch_margin_ms: 30,
+            max_latency_ms: 300,
+            max_loss_percent: 20.0,
+            failover_after: 3,
+            health_interval_secs: 10,
+            preferred: None,
+        }
+    }
+}
+
+/// PoP chosen by the user or an administrator
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreferredPop {
+    pub pop_id: String,
+    pub mode: PreferredMode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreferredMode {
+    /// Used while healthy; failed over from like any other PoP
+    Prefer,
+    /// Never left, even when degraded
+    Pin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionReason {
+    Pinned,
+    Preferred,
+    /// Stayed on the current PoP
+    Sticky,
+    BestScore,
+    Failover,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PopSelection {
+    pub gateway: GatewayEndpoint,
+    /// A pinned PoP is selected even when this shows it unreachable
+    pub probe: ProbeResult,
+    pub reason: SelectionReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum PopHealth {
+    Healthy { latency_ms: u32, loss_percent: f32 },
+    Degraded { reason: String, consecutive: u32 },
+    /// Degraded long enough to fail over
+    Failed { reason: String },
+}
+
 impl GatewaySelector {
     pub fn new() -> Self {
         Self {
             gateways: parking_lot::RwLock::new(Vec::new()),
             probe_results: parking_lot::RwLock::new(Vec::new()),
+            policy: parking_lot::RwLock::new(PopSelectionPolicy::default()),
+            client_location: parking_lot::RwLock::new(None),
+            current: parking_lot::RwLock::new(None),
+            degraded_checks: AtomicU32::new(0),
         }
     }
     
+    pub fn with_policy(self, policy: PopSelectionPolicy) -> Self {
+        *self.policy.write() = policy;
+        self
+    }
+    
+    pub fn policy(&self) -> PopSelectionPolicy {
+        self.policy.read().clone()
+    }
+    
+    /// Change the policy, e.g. when the user picks a preferred PoP
+    pub fn set_policy(&self, policy: PopSelectionPolicy) {
+        *self.policy.write() = policy;
+    }
+    
     /// Update available gateways from server
     pub fn update_gateways(&self, gateways: Vec<GatewayEndpoint>) {
         *self.gateways.write() = gateways;
     }
     
+    /// Replace the gateways with the controller's PoP catalog
+    pub fn update_catalog(&self, catalog: PopCatalog) {
+        *self.client_location.write() = catalog.client_location;
+        self.update_gateways(catalog.pops);
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.gateways.read().is_empty()
+    }
+    
+    /// PoP the tunnel is on
+    pub fn current(&self) -> Option<GatewayEndpoint> {
+        let current = self.current.read().clone()?;
+        self.gateways.read().iter().find(|g| g.id == current).cloned()
+    }
+    
+    /// Record the PoP the tunnel is on after a switch
+    pub fn set_current(&self, pop_id: &str) {
+        *self.current.write() = Some(pop_id.to_string());
+        self.degraded_checks.store(0, Ordering::Relaxed);
+    }
+    
+    /// Probe the nearest PoPs and choose one
+    ///
+    /// A pinned PoP always wins and a preferred one wins while it is
+    /// healthy. Otherwise the current PoP is kept unless it is degraded or
+    /// another PoP beats its score by the switch margin.
+    pub async fn select(&self) -> Option<PopSelection> {
+        self.select_excluding(None).await
+    }
+    
+    /// Choose a PoP other than the current one
+    pub async fn fail_over(&self) -> Option<PopSelection> {
+        let current = self.current.read().clone();
+        let mut selection = self.select_excluding(current.as_deref()).await?;
+        selection.reason = SelectionReason::Failover;
+        Some(selection)
+    }
+    
+    async fn select_excluding(&self, excluded: Option<&str>) -> Option<PopSelection> {
+        let policy = self.policy();
+        let gateways = self.gateways.read().clone();
+        
+        // A pinned PoP missing from the catalog is selected around
+        let pinned = match &policy.preferred {
+            Some(PreferredPop { pop_id, mode: PreferredMode::Pin }) => gateways.iter().find(|g| &g.id == pop_id).cloned(),
+            _ => None,
+        };
+        if let Some(gateway) = pinned {
+            let probe = self.probe(&gateway, &policy).await;
+            return Some(self.choose(gateway, probe, SelectionReason::Pinned));
+        }
+        
+        let candidates: Vec<GatewayEndpoint> = self.candidates(&gateways, &policy)
+            .into_iter()
+            .filter(|g| Some(g.id.as_str()) != excluded)
+            .collect();
+        let mut scored = Vec::with_capacity(candidates.len());
+        for gateway in candidates {
+            let probe = self.probe(&gateway, &policy).await;
+            scored.push((gateway, probe));
+        }
+        *self.probe_results.write() = scored.iter().map(|(_, p)| p.clone()).collect();
+        
+        let healthy = |probe: &ProbeResult| probe.success && degradation(probe, &policy).is_none();
+        
+        if let Some(PreferredPop { pop_id, .. }) = &policy.preferred {
+            if let Some((gateway, probe)) = scored.iter().find(|(g, p)| &g.id == pop_id && healthy(p)) {
+                return Some(self.choose(gateway.clone(), probe.clone(), SelectionReason::Preferred));
+            }
+        }
+        
+        let best = scored.iter()
+            .filter_map(|(g, p)| score(g, p, &policy).map(|s| (s, g, p)))
+            .min_by_key(|(s, _, _)| *s)?;
+        
+        let current = self.current.read().clone();
+        let sticky = scored.iter().find(|(g, p)| Some(&g.id) == current.as_ref() && healthy(p));
+        if let Some((gateway, probe)) = sticky {
+            let current_score = score(gateway, probe, &policy).unwrap_or(u32::MAX);
+            if current_score <= best.0.saturating_add(policy.switch_margin_ms) {
+                return Some(self.choose(gateway.clone(), probe.clone(), SelectionReason::Sticky));
+            }
+        }
+        
+        let (_, gateway, probe) = best;
+        Some(self.choose(gateway.clone(), probe.clone(), SelectionReason::BestScore))
+    }
+    
+    fn choose(&self, gateway: GatewayEndpoint, probe: ProbeResult, reason: SelectionReason) -> PopSelection {
+        tracing::info!("Selected PoP {} ({:?}, {:?} ms)", gateway.id, reason, probe.latency_ms);
+        self.set_current(&gateway.id);
+        PopSelection { gateway, probe, reason }
+    }
+    
+    /// Nearest PoPs to the client, plus the preferred one
+    fn candidates(&self, gateways: &[GatewayEndpoint], policy: &PopSelectionPolicy) -> Vec<GatewayEndpoint> {
+        let Some(location) = self.client_location.read().clone() else {
+            return gateways.to_vec();
+        };
+        let mut nearest = gateways.to_vec();
+        nearest.sort_by(|a, b| {
+            location.distance_km(&a.location).total_cmp(&location.distance_km(&b.location))
+        });
+        
+        let keep = |g: &GatewayEndpoint| {
+            policy.preferred.as_ref().is_some_and(|p| p.pop_id == g.id)
+                || self.current.read().as_ref() == Some(&g.id)
+        };
+        nearest.into_iter()
+            .enumerate()
+            .filter(|(i, g)| *i < policy.candidates.max(1) || keep(g))
+            .map(|(_, g)| g)
+            .collect()
+    }
+    
+    /// Probe a PoP repeatedly for RTT and loss
+    pub async fn probe(&self, gateway: &GatewayEndpoint, policy: &PopSelectionPolicy) -> ProbeResult {
+        let timeout = Duration::from_millis(policy.probe_timeout_ms);
+        let attempts = policy.probes_per_pop.max(1);
+        let mut latencies = Vec::with_capacity(attempts as usize);
+        for _ in 0..attempts {
+            if let Some(latency) = self.probe_rtt(&gateway.host, gateway.port, timeout).await {
+                latencies.push(latency);
+            }
+        }
+        latencies.sort_unstable();
+        
+        ProbeResult {
+            gateway_id: gateway.id.clone(),
+            latency_ms: latencies.get(latencies.len() / 2).copied(),
+            loss_percent: (attempts as usize - latencies.len()) as f32 * 100.0 / attempts as f32,
+            success: !latencies.is_empty(),
+            probed_at: chrono::Utc::now(),
+        }
+    }
+    
+    /// Probe the current PoP and decide whether to fail over
+    pub async fn check_health(&self) -> Option<PopHealth> {
+        let gateway = self.current()?;
+        let policy = self.policy();
+        let probe = self.probe(&gateway, &policy).await;
+        
+        let Some(reason) = degradation(&probe, &policy) else {
+            self.degraded_checks.store(0, Ordering::Relaxed);
+            return Some(PopHealth::Healthy {
+                latency_ms: probe.latency_ms.unwrap_or_default(),
+                loss_percent: probe.loss_percent,
+            });
+        };
+        
+        let consecutive = self.degraded_checks.fetch_add(1, Ordering::Relaxed) + 1;
+        let pinned = matches!(
+            &policy.preferred,
+            Some(PreferredPop { pop_id, mode: PreferredMode::Pin }) if *pop_id == gateway.id
+        );
+        if consecutive >= policy.failover_after.max(1) && !pinned {
+            Some(PopHealth::Failed { reason })
+        } else {
+            Some(PopHealth::Degraded { reason, consecutive })
+        }
+    }
+    
     /// Probe all gateways and select best
     pub async fn select_best(&self) -> Option<GatewayEndpoint> {
         let gateways = self.gateways.read().clone();
@@ -67,6 +388,7 @@ impl GatewaySelector {
             results.push(ProbeResult {
                 gateway_id: gateway.id.clone(),
                 latency_ms: latency,
+                loss_percent: if latency.is_some() { 0.0 } else { 100.0 },
                 success: latency.is_some(),
                 probed_at: chrono::Utc::now(),
             });
@@ -113,6 +435,22 @@ impl GatewaySelector {
         }
     }
     
+    /// Round trip of a TCP connect; a refused connection still answered
+    async fn probe_rtt(&self, host: &str, port: u16, timeout: Duration) -> Option<u32> {
+        let addr = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => tokio::net::lookup_host((host, port)).await.ok()?.next()?,
+        };
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => Some(start.elapsed().as_millis() as u32),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                Some(start.elapsed().as_millis() as u32)
+            }
+            _ => None,
+        }
+    }
+    
     async fn probe_udp(&self, addr: &str) -> Option<u32> {
         let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await.ok()?;
         let start = Instant::now();
@@ -151,3 +489,200 @@ impl GatewaySelector {
 impl Default for GatewaySelector {
     fn default() -> Self { Self::new() }
 }
+
+/// Latency plus penalties for loss and load; `None` when unreachable
+fn score(gateway: &GatewayEndpoint, probe: &ProbeResult, policy: &PopSelectionPolicy) -> Option<u32> {
+    let latency = probe.latency_ms?;
+    let loss = (probe.loss_percent * policy.loss_penalty_ms as f32) as u32;
+    Some(latency.saturating_add(loss).saturating_add(gateway.capacity.load_percent()))
+}
+
+/// Why a probe counts as degraded, if it does
+fn degradation(probe: &ProbeResult, policy: &PopSelectionPolicy) -> Option<String> {
+    match probe.latency_ms {
+        None => Some("unreachable".to_string()),
+        Some(_) if probe.loss_percent > policy.max_loss_percent => {
+            Some(format!("{:.0}% loss", probe.loss_percent))
+        }
+        Some(latency) if latency > policy.max_latency_ms => Some(format!("{} ms RTT", latency)),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loopback port nobody listens on; a refused connect answers at once
+    fn closed_port() -> u16 {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    fn pop(id: &str, host: &str, port: u16, city: (f64, f64), load: u32) -> GatewayEndpoint {
+        GatewayEndpoint {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            host: host.to_string(),
+            port,
+            public_key: format!("{}-key", id),
+            location: GeoLocation {
+                city: id.to_string(),
+                country: "XX".to_string(),
+                latitude: city.0,
+                longitude: city.1,
+            },
+            capacity: GatewayCapacity { max_connections: 100, current_connections: load, bandwidth_mbps: 1000 },
+        }
+    }
+
+    const LONDON: (f64, f64) = (51.5074, -0.1278);
+    const PARIS: (f64, f64) = (48.8566, 2.3522);
+    const NEW_YORK: (f64, f64) = (40.7128, -74.0060);
+
+    /// `busy` and `idle` answer, `dead` never resolves
+    fn selector(policy: PopSelectionPolicy) -> GatewaySelector {
+        let port = closed_port();
+        let selector = GatewaySelector::new().with_policy(PopSelectionPolicy {
+            probes_per_pop: 1,
+            probe_timeout_ms: 200,
+            failover_after: 2,
+            ..policy
+        });
+        selector.update_gateways(vec![
+            pop("busy", "127.0.0.1", port, LONDON, 30),
+            pop("idle", "127.0.0.1", port, PARIS, 5),
+            pop("dead", "pop.invalid", port, NEW_YORK, 0),
+        ]);
+        selector
+    }
+
+    fn probe(latency_ms: Option<u32>, loss_percent: f32) -> ProbeResult {
+        ProbeResult {
+            gateway_id: "p".to_string(),
+            latency_ms,
+            loss_percent,
+            success: latency_ms.is_some(),
+            probed_at: chrono::Utc::now(),
+        }
+    }
+
+    fn preferred(pop_id: &str, mode: PreferredMode) -> PopSelectionPolicy {
+        PopSelectionPolicy {
+            preferred: Some(PreferredPop { pop_id: pop_id.to_string(), mode }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_endpoint_distance_and_load() {
+        assert_eq!(pop("a", "gw.example.com", 51820, LONDON, 0).endpoint(), "gw.example.com:51820");
+        assert_eq!(pop("a", "2001:db8::1", 51820, LONDON, 0).endpoint(), "[2001:db8::1]:51820");
+        assert_eq!(pop("a", "[2001:db8::1]", 51820, LONDON, 0).endpoint(), "[2001:db8::1]:51820");
+
+        let london = pop("l", "h", 1, LONDON, 0).location;
+        let paris = pop("p", "h", 1, PARIS, 0).location;
+        assert!((london.distance_km(&paris) - 344.0).abs() < 5.0);
+        assert_eq!(london.distance_km(&london), 0.0);
+
+        let full = GatewayCapacity { max_connections: 200, current_connections: 150, bandwidth_mbps: 1 };
+        assert_eq!(full.load_percent(), 75);
+        let unknown = GatewayCapacity { max_connections: 0, current_connections: 3, bandwidth_mbps: 1 };
+        assert_eq!(unknown.load_percent(), 300);
+    }
+
+    #[test]
+    fn test_score_and_degradation() {
+        let policy = PopSelectionPolicy::default();
+        let gateway = pop("a", "h", 1, LONDON, 20);
+        // 40 ms + 25% loss at 10 ms per percent + 20% load
+        assert_eq!(score(&gateway, &probe(Some(40), 25.0), &policy), Some(310));
+        assert_eq!(score(&gateway, &probe(None, 100.0), &policy), None);
+
+        assert_eq!(degradation(&probe(Some(40), 0.0), &policy), None);
+        assert_eq!(degradation(&probe(None, 100.0), &policy).as_deref(), Some("unreachable"));
+        assert_eq!(degradation(&probe(Some(40), 25.0), &policy).as_deref(), Some("25% loss"));
+        assert_eq!(degradation(&probe(Some(301), 0.0), &policy).as_deref(), Some("301 ms RTT"));
+    }
+
+    #[test]
+    fn test_candidates_are_nearest_plus_preferred_and_current() {
+        let selector = GatewaySelector::new();
+        selector.update_catalog(PopCatalog {
+            pops: vec![pop("nyc", "h", 1, NEW_YORK, 0), pop("par", "h", 1, PARIS, 0), pop("lon", "h", 1, LONDON, 0)],
+            client_location: Some(pop("me", "h", 1, (51.0, 0.0), 0).location),
+        });
+        let ids = |policy: &PopSelectionPolicy| -> Vec<String> {
+            let gateways = selector.gateways.read().clone();
+            selector.candidates(&gateways, policy).into_iter().map(|g| g.id).collect()
+        };
+
+        let nearest = PopSelectionPolicy { candidates: 1, ..Default::default() };
+        assert_eq!(ids(&nearest), vec!["lon"]);
+        assert_eq!(ids(&PopSelectionPolicy { candidates: 1, ..preferred("nyc", PreferredMode::Prefer) }), vec!["lon", "nyc"]);
+        selector.set_current("par");
+        assert_eq!(ids(&nearest), vec!["lon", "par"]);
+        assert_eq!(selector.current().unwrap().id, "par");
+    }
+
+    #[tokio::test]
+    async fn test_select_best_score_and_stickiness() {
+        let pops = selector(PopSelectionPolicy::default());
+        let selection = pops.select().await.unwrap();
+        assert_eq!((selection.gateway.id.as_str(), selection.reason), ("idle", SelectionReason::BestScore));
+        assert_eq!(pops.get_probe_results().len(), 3);
+        assert!(!pops.get_probe_results().iter().find(|p| p.gateway_id == "dead").unwrap().success);
+
+        // Within the switch margin the client stays where it is
+        pops.set_current("busy");
+        let selection = pops.select().await.unwrap();
+        assert_eq!((selection.gateway.id.as_str(), selection.reason), ("busy", SelectionReason::Sticky));
+
+        pops.set_policy(PopSelectionPolicy { switch_margin_ms: 10, probes_per_pop: 1, probe_timeout_ms: 200, ..Default::default() });
+        let selection = pops.select().await.unwrap();
+        assert_eq!((selection.gateway.id.as_str(), selection.reason), ("idle", SelectionReason::BestScore));
+
+        let selection = pops.fail_over().await.unwrap();
+        assert_eq!((selection.gateway.id.as_str(), selection.reason), ("busy", SelectionReason::Failover));
+        assert_eq!(pops.current().unwrap().id, "busy");
+    }
+
+    #[tokio::test]
+    async fn test_preferred_and_pinned_pops() {
+        let selection = selector(preferred("busy", PreferredMode::Prefer)).select().await.unwrap();
+        assert_eq!((selection.gateway.id.as_str(), selection.reason), ("busy", SelectionReason::Preferred));
+
+        // An unhealthy preference is passed over, a pin is not
+        let selection = selector(preferred("dead", PreferredMode::Prefer)).select().await.unwrap();
+        assert_eq!(selection.gateway.id, "idle");
+        let selection = selector(preferred("dead", PreferredMode::Pin)).select().await.unwrap();
+        assert_eq!((selection.gateway.id.as_str(), selection.reason), ("dead", SelectionReason::Pinned));
+        assert!(!selection.probe.success);
+
+        // A pin to a PoP missing from the catalog is ignored
+        let selection = selector(preferred("gone", PreferredMode::Pin)).select().await.unwrap();
+        assert_eq!(selection.gateway.id, "idle");
+    }
+
+    #[tokio::test]
+    async fn test_health_checks_fail_over_after_consecutive_degradations() {
+        let pops = selector(PopSelectionPolicy::default());
+        assert!(pops.check_health().await.is_none(), "no current PoP");
+
+        pops.set_current("idle");
+        assert!(matches!(pops.check_health().await, Some(PopHealth::Healthy { .. })));
+
+        pops.set_current("dead");
+        assert_eq!(
+            pops.check_health().await,
+            Some(PopHealth::Degraded { reason: "unreachable".to_string(), consecutive: 1 })
+        );
+        assert_eq!(pops.check_health().await, Some(PopHealth::Failed { reason: "unreachable".to_string() }));
+
+        // A pinned PoP stays degraded instead of failing
+        let pinned = selector(preferred("dead", PreferredMode::Pin));
+        pinned.set_current("dead");
+        pinned.check_health().await;
+        assert!(matches!(pinned.check_health().await, Some(PopHealth::Degraded { consecutive: 2, .. })));
+    }
+}
@@ -5,6 +5,8 @@
 //! - Device posture collection
 //! - Always-on protection
 //! - Split tunneling
+//! - Roaming and captive portal handling
+//! - PoP selection and latency-based failover
 //!
 //! # Platform Support
 //! - Windows 10/11 (x64, ARM64)
@@ -55,6 +57,9 @@ pub mod power;
 pub mod keychain;
 pub mod gateway;
 pub mod wireguard;
+pub mod split_tunnel;
+pub mod captive;
+pub mod network;
 
 #[cfg(feature = "ffi")]
 pub mod ffi;
@@ -87,6 +92,9 @@ pub struct ClientFeatures {
     pub dns_protection: bool,
     pub posture_check: bool,
     pub auto_reconnect: bool,
+    /// Split tunnel rules used when the controller sends none
+    #[serde(default)]
+    pub split_tunnel_rules: split_tunnel::SplitTunnelConfig,
 }
 
 impl Default for ClientFeatures {
@@ -97,6 +105,7 @@ impl Default for ClientFeatures {
             dns_protection: true,
             posture_check: true,
             auto_reconnect: true,
+            split_tunnel_rules: split_tunnel::SplitTunnelConfig::default(),
         }
     }
 }
@@ -107,6 +116,23 @@ pub struct ConnectionSettings {
     pub max_reconnect_attempts: u32,
     pub keepalive_interval_secs: u64,
     pub mtu: u16,
+    /// How long the tunnel stays off for a captive portal sign-in
+    #[serde(default = "default_pass_through_secs")]
+    pub captive_pass_through_secs: u64,
+    /// How long to wait for a handshake after a network change
+    #[serde(default = "default_handshake_timeout_ms")]
+    pub handshake_timeout_ms: u64,
+    /// PoP probing, failover and preferred PoP
+    #[serde(default)]
+    pub pop_selection: gateway::PopSelectionPolicy,
+}
+
+fn default_pass_through_secs() -> u64 {
+    300
+}
+
+fn default_handshake_timeout_ms() -> u64 {
+    5000
 }
 
 impl Default for ConnectionSettings {
@@ -116,6 +142,9 @@ impl Default for ConnectionSettings {
             max_reconnect_attempts: 10,
             keepalive_interval_secs: 25,
             mtu: 1420,
+            captive_pass_through_secs: default_pass_through_secs(),
+            handshake_timeout_ms: default_handshake_timeout_ms(),
+            pop_selection: gateway::PopSelectionPolicy::default(),
         }
     }
 }
@@ -129,6 +158,8 @@ pub enum ClientState {
     PostureCheck,
     Connected,
     Reconnecting,
+    /// Tunnel suspended while the user signs in to a captive portal
+    PassThrough,
     Error,
 }
 
@@ -138,6 +169,9 @@ pub struct ConnectionStatus {
     pub state: ClientState,
     pub connected_at: Option<DateTime<Utc>>,
     pub server_ip: Option<String>,
+    /// PoP the tunnel is connected to
+    #[serde(default)]
+    pub pop_id: Option<String>,
     pub client_ip: Option<String>,
     pub bytes_sent: u64,
     pub bytes_received: u64,
@@ -151,6 +185,7 @@ impl Default for ConnectionStatus {
             state: ClientState::Disconnected,
             connected_at: None,
             server_ip: None,
+            pop_id: None,
             client_ip: None,
             bytes_sent: 0,
             bytes_received: 0,
@@ -174,6 +209,11 @@ pub struct SaseClient {
     policy: policy::PolicyEngine,
     auth: auth::AuthManager,
     dns: dns::DnsManager,
+    split_tunnel: split_tunnel::SplitTunnelEngine,
+    routes: split_tunnel::RouteProgrammer,
+    gateways: gateway::GatewaySelector,
+    captive: captive::CaptivePortalDetector,
+    network: network::NetworkMonitor,
     event_tx: tokio::sync::broadcast::Sender<ClientEvent>,
 }
 
@@ -185,6 +225,16 @@ pub enum ClientEvent {
     Disconnected { reason: String },
     PostureChanged(posture::PostureResult),
     PolicyUpdated,
+    SplitTunnelUpdated { version: u64 },
+    SplitTunnelStats(split_tunnel::SplitTunnelTelemetry),
+    NetworkChanged(network::NetworkChange),
+    Roamed { endpoint: String },
+    CaptivePortalDetected { portal_url: Option<String>, pass_through_secs: u64 },
+    PassThroughCountdown { remaining_secs: u64 },
+    PassThroughEnded { reason: String },
+    PopSelected { pop_id: String, name: String, latency_ms: Option<u32>, reason: gateway::SelectionReason },
+    PopDegraded { pop_id: String, reason: String, consecutive: u32 },
+    PopFailover { from: Option<String>, to: String, reason: String },
     Error { code: String, message: String },
     Stats { bytes_sent: u64, bytes_received: u64 },
 }
@@ -203,6 +253,11 @@ impl SaseClient {
             policy: policy::PolicyEngine::new(),
             auth: auth::AuthManager::new(&config.server_url, &config.tenant_id),
             dns: dns::DnsManager::new(),
+            split_tunnel: split_tunnel::SplitTunnelEngine::new(),
+            routes: split_tunnel::RouteProgrammer::new(),
+            gateways: gateway::GatewaySelector::new().with_policy(config.connection.pop_selection.clone()),
+            captive: captive::CaptivePortalDetector::new(),
+            network: network::NetworkMonitor::default(),
             event_tx,
         }
     }
@@ -222,27 +277,43 @@ impl SaseClient {
         self.emit_event(ClientEvent::PostureChanged(posture_result.clone()));
         
         // Step 3: Get tunnel config from server
-        let tunnel_config = self.auth.get_tunnel_config(&auth_result.token).await
+        let mut tunnel_config = self.auth.get_tunnel_config(&auth_result.token).await
             .map_err(|e| ClientError::ConfigFailed(e.to_string()))?;
         
+        // Step 4: Select PoP; the server's endpoint is the fallback
+        let pop = self.select_pop(&auth_result.token).await;
+        if let Some(selection) = &pop {
+            tunnel_config.server_endpoint = selection.gateway.endpoint();
+            tunnel_config.server_public_key = selection.gateway.public_key.clone();
+        }
+        
         // Clone fields we need after moving tunnel_config
         let dns_servers = tunnel_config.dns_servers.clone();
         let policies = tunnel_config.policies.clone();
         let server_endpoint = tunnel_config.server_endpoint.clone();
         let client_ip = tunnel_config.client_ip.clone();
+        let split_tunnel_rules = tunnel_config.split_tunnel.clone();
         
-        // Step 4: Establish tunnel
+        // Step 5: Establish tunnel
         self.tunnel.connect(tunnel_config).await
             .map_err(|e| ClientError::TunnelFailed(e.to_string()))?;
         
-        // Step 5: Configure DNS
+        // Step 6: Configure DNS
         if self.config.features.dns_protection {
             self.dns.configure(&dns_servers).await?;
         }
         
-        // Step 6: Apply policies
+        // Step 7: Apply policies
         self.policy.apply(&policies).await?;
         
+        // Step 8: Program split tunnel rules
+        if self.config.features.split_tunnel {
+            let rules = split_tunnel_rules
+                .or_else(|| split_tunnel::SplitTunnelConfig::from_policies(&policies))
+                .unwrap_or_else(|| self.config.features.split_tunnel_rules.clone());
+            self.apply_split_tunnel(rules).await?;
+        }
+        
         // Update status
         {
             let mut status = self.status.write();
@@ -253,6 +324,9 @@ impl SaseClient {
         }
         
         self.set_state(ClientState::Connected);
+        if let Some(selection) = &pop {
+            self.record_pop(selection);
+        }
         self.emit_event(ClientEvent::Connected {
             server: server_endpoint,
             client_ip,
@@ -269,6 +343,9 @@ impl SaseClient {
         // Restore DNS
         self.dns.restore().await?;
         
+        // Remove split tunnel routes
+        self.routes.clear().await?;
+        
         // Close tunnel
         self.tunnel.disconnect().await?;
         
@@ -278,6 +355,7 @@ impl SaseClient {
             let mut status = self.status.write();
             status.state = ClientState::Disconnected;
             status.connected_at = None;
+            status.pop_id = None;
         }
         
         self.emit_event(ClientEvent::Disconnected {
@@ -302,6 +380,340 @@ impl SaseClient {
         self.event_tx.subscribe()
     }
     
+    /// Apply a split tunnel rule set, e.g. one pushed by the controller
+    ///
+    /// Routes and per-domain DNS are reprogrammed when the rules change;
+    /// re-sending the current version is a no-op.
+    pub async fn apply_split_tunnel(&self, rules: split_tunnel::SplitTunnelConfig) -> Result<(), ClientError> {
+        if !self.split_tunnel.apply(&rules)? {
+            return Ok(());
+        }
+        
+        self.sync_split_routes().await?;
+        let tunnel_dns = self.tunnel.config().map(|c| c.dns_servers).unwrap_or_default();
+        self.dns.configure_split(&tunnel_dns, &self.split_tunnel.dns_plan()).await?;
+        
+        self.emit_event(ClientEvent::SplitTunnelUpdated { version: rules.version });
+        Ok(())
+    }
+    
+    /// Feed a DNS answer so its addresses follow the matching domain rule
+    pub async fn learn_dns_resolution(
+        &self,
+        domain: &str,
+        addresses: &[std::net::IpAddr],
+        ttl: std::time::Duration,
+    ) -> Result<(), ClientError> {
+        if self.split_tunnel.learn_resolution(domain, addresses, ttl).is_some() {
+            self.sync_split_routes().await?;
+        }
+        Ok(())
+    }
+    
+    /// Split tunnel rules engine, for classifying and counting flows
+    pub fn split_tunnel(&self) -> &split_tunnel::SplitTunnelEngine {
+        &self.split_tunnel
+    }
+    
+    /// Send per-rule split tunnel counters to the controller
+    pub async fn report_split_tunnel_telemetry(&self) -> Result<split_tunnel::SplitTunnelTelemetry, ClientError> {
+        let telemetry = self.split_tunnel.telemetry();
+        let token = self.auth.get_token()
+            .ok_or_else(|| ClientError::AuthFailed("Not authenticated".to_string()))?;
+        self.auth.report_split_tunnel_telemetry(&token, &telemetry).await
+            .map_err(|e| ClientError::ConfigFailed(e.to_string()))?;
+        
+        self.emit_event(ClientEvent::SplitTunnelStats(telemetry.clone()));
+        Ok(telemetry)
+    }
+    
+    /// Gateways to choose from when re-selecting after a network change
+    pub fn update_gateways(&self, gateways: Vec<gateway::GatewayEndpoint>) {
+        self.gateways.update_gateways(gateways);
+    }
+    
+    /// Known PoPs, lowest latency first
+    pub fn pops(&self) -> Vec<(gateway::GatewayEndpoint, Option<u32>)> {
+        self.gateways.get_sorted_gateways()
+    }
+    
+    /// Set or clear the preferred PoP, moving the tunnel if connected
+    pub async fn set_preferred_pop(&self, preferred: Option<gateway::PreferredPop>) -> Result<(), ClientError> {
+        let mut policy = self.gateways.policy();
+        policy.preferred = preferred;
+        self.gateways.set_policy(policy);
+        
+        if self.state() != ClientState::Connected {
+            return Ok(());
+        }
+        if let Some(selection) = self.gateways.select().await {
+            self.move_to_pop(&selection).await?;
+        }
+        Ok(())
+    }
+    
+    /// Watch the connected PoP and fail over when it degrades
+    ///
+    /// Runs until the future is dropped; spawn it for the lifetime of the
+    /// client.
+    pub async fn run_pop_monitor(&self) {
+        let interval = std::time::Duration::from_secs(self.gateways.policy().health_interval_secs.max(1));
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        
+        loop {
+            ticker.tick().await;
+            
+            // A failed failover leaves the client reconnecting to the new
+            // PoP, whose health decides whether to move on again
+            if !matches!(self.state(), ClientState::Connected | ClientState::Reconnecting) {
+                continue;
+            }
+            
+            match self.gateways.check_health().await {
+                Some(gateway::PopHealth::Degraded { reason, consecutive }) => {
+                    let pop_id = self.gateways.current().map(|g| g.id).unwrap_or_default();
+                    tracing::warn!("PoP {} degraded: {} ({} checks)", pop_id, reason, consecutive);
+                    self.emit_event(ClientEvent::PopDegraded { pop_id, reason, consecutive });
+                }
+                Some(gateway::PopHealth::Failed { reason }) => {
+                    if let Err(e) = self.fail_over(&reason).await {
+                        tracing::warn!("PoP failover failed: {}", e);
+                        self.emit_event(ClientEvent::Error {
+                            code: "POP_FAILOVER_FAILED".to_string(),
+                            message: e.to_string(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    /// Watch for network changes and captive portals
+    ///
+    /// Runs until the future is dropped; spawn it for the lifetime of the
+    /// client.
+    pub async fn run_network_monitor(&self) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut next_poll = std::time::Instant::now();
+        
+        loop {
+            ticker.tick().await;
+            
+            if let Some(window) = self.tunnel.pass_through() {
+                self.tick_pass_through(window).await;
+            }
+            
+            if std::time::Instant::now() >= next_poll {
+                next_poll = std::time::Instant::now() + self.network.poll_interval();
+                let changes = self.network.poll().await;
+                if !changes.is_empty() {
+                    self.handle_network_changes(&changes).await;
+                }
+            }
+        }
+    }
+    
+    /// Probe for a captive portal and open pass-through if one is found
+    pub async fn check_captive_portal(&self) -> Result<captive::PortalStatus, ClientError> {
+        // Probes must not go into a tunnel the portal is blocking
+        let pass_through = std::time::Duration::from_secs(self.config.connection.captive_pass_through_secs);
+        let already_open = self.tunnel.pass_through().is_some();
+        if !already_open {
+            self.tunnel.enter_pass_through(pass_through).await?;
+        }
+        
+        let status = self.captive.detect().await;
+        match &status {
+            captive::PortalStatus::Captive { portal_url } => {
+                tracing::warn!("Captive portal detected: {:?}", portal_url);
+                self.set_state(ClientState::PassThrough);
+                self.emit_event(ClientEvent::CaptivePortalDetected {
+                    portal_url: portal_url.clone(),
+                    pass_through_secs: pass_through.as_secs(),
+                });
+            }
+            _ if !already_open => {
+                self.tunnel.exit_pass_through().await?;
+            }
+            _ => {}
+        }
+        Ok(status)
+    }
+    
+    /// End captive portal pass-through early and bring the tunnel back
+    pub async fn end_pass_through(&self, reason: &str) -> Result<(), ClientError> {
+        if self.tunnel.pass_through().is_none() {
+            return Ok(());
+        }
+        self.tunnel.exit_pass_through().await?;
+        self.set_state(ClientState::Connected);
+        self.emit_event(ClientEvent::PassThroughEnded { reason: reason.to_string() });
+        Ok(())
+    }
+    
+    async fn tick_pass_through(&self, window: captive::PassThroughWindow) {
+        let result = if window.expired() {
+            self.end_pass_through("Pass-through expired").await
+        } else {
+            let remaining_secs = window.remaining().as_secs();
+            self.emit_event(ClientEvent::PassThroughCountdown { remaining_secs });
+            
+            // Close the window as soon as the user has signed in
+            if remaining_secs % 5 == 0 && self.captive.detect().await == captive::PortalStatus::Open {
+                self.end_pass_through("Signed in to network").await
+            } else {
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to end pass-through: {}", e);
+        }
+    }
+    
+    async fn handle_network_changes(&self, changes: &[network::NetworkChange]) {
+        for change in changes {
+            self.emit_event(ClientEvent::NetworkChanged(change.clone()));
+        }
+        
+        let state = self.state();
+        if !matches!(state, ClientState::Connected | ClientState::Reconnecting | ClientState::PassThrough) {
+            return;
+        }
+        
+        if changes.iter().any(|c| c.triggers_roam()) {
+            if let Err(e) = self.roam().await {
+                tracing::warn!("Roaming failed: {}", e);
+                self.emit_event(ClientEvent::Error {
+                    code: "ROAM_FAILED".to_string(),
+                    message: e.to_string(),
+                });
+            }
+        } else if changes.iter().any(|c| c.is_offline()) {
+            self.set_state(ClientState::Reconnecting);
+        }
+    }
+    
+    /// Carry the tunnel over to a new network path
+    ///
+    /// The tunnel interface and address are kept, so sessions inside the
+    /// tunnel survive; only the outer path and possibly the gateway change.
+    async fn roam(&self) -> Result<(), ClientError> {
+        // A portal on the previous network says nothing about this one
+        if self.tunnel.pass_through().is_some() {
+            self.end_pass_through("Network changed").await?;
+        }
+        
+        let started = Utc::now();
+        let current = self.tunnel.config().map(|c| c.server_endpoint);
+        let endpoint = match self.gateways.select().await {
+            Some(selection) if current.as_deref() != Some(selection.gateway.endpoint().as_str()) => {
+                let endpoint = selection.gateway.endpoint();
+                self.tunnel.switch_endpoint(&endpoint, &selection.gateway.public_key).await?;
+                self.record_pop(&selection);
+                endpoint
+            }
+            _ => {
+                self.tunnel.rehandshake().await?;
+                current.unwrap_or_default()
+            }
+        };
+        
+        let timeout = std::time::Duration::from_millis(self.config.connection.handshake_timeout_ms);
+        if self.tunnel.wait_for_handshake(started, timeout).await {
+            self.set_state(ClientState::Connected);
+            self.emit_event(ClientEvent::Roamed { endpoint });
+            return Ok(());
+        }
+        
+        // No handshake: the new network may be holding traffic for a portal
+        match self.check_captive_portal().await? {
+            captive::PortalStatus::Captive { .. } => Ok(()),
+            _ => {
+                self.set_state(ClientState::Reconnecting);
+                Err(ClientError::TunnelFailed(format!("No handshake with {} after network change", endpoint)))
+            }
+        }
+    }
+    
+    /// Refresh the PoP catalog and choose a PoP to connect to
+    async fn select_pop(&self, token: &str) -> Option<gateway::PopSelection> {
+        match self.auth.get_pop_catalog(token).await {
+            Ok(catalog) => self.gateways.update_catalog(catalog),
+            // The last catalog, if any, is still worth selecting from
+            Err(e) => tracing::warn!("Failed to fetch PoP catalog: {}", e),
+        }
+        if self.gateways.is_empty() {
+            return None;
+        }
+        
+        let selection = self.gateways.select().await;
+        if selection.is_none() {
+            tracing::warn!("No PoP reachable, using the server's endpoint");
+        }
+        selection
+    }
+    
+    /// Move the tunnel off a degraded PoP
+    async fn fail_over(&self, reason: &str) -> Result<(), ClientError> {
+        let from = self.gateways.current().map(|g| g.id);
+        let selection = self.gateways.fail_over().await
+            .ok_or_else(|| ClientError::TunnelFailed(format!("No PoP to fail over to ({})", reason)))?;
+        
+        tracing::warn!("Failing over from PoP {:?} to {}: {}", from, selection.gateway.id, reason);
+        self.set_state(ClientState::Reconnecting);
+        self.move_to_pop(&selection).await?;
+        self.set_state(ClientState::Connected);
+        self.emit_event(ClientEvent::PopFailover {
+            from,
+            to: selection.gateway.id.clone(),
+            reason: reason.to_string(),
+        });
+        Ok(())
+    }
+    
+    /// Switch the tunnel to a selected PoP and wait for its handshake
+    async fn move_to_pop(&self, selection: &gateway::PopSelection) -> Result<(), ClientError> {
+        let endpoint = selection.gateway.endpoint();
+        if self.tunnel.config().is_some_and(|c| c.server_endpoint == endpoint) {
+            return Ok(());
+        }
+        
+        let started = Utc::now();
+        self.tunnel.switch_endpoint(&endpoint, &selection.gateway.public_key).await?;
+        let timeout = std::time::Duration::from_millis(self.config.connection.handshake_timeout_ms);
+        if !self.tunnel.wait_for_handshake(started, timeout).await {
+            return Err(ClientError::TunnelFailed(format!("No handshake with PoP {}", selection.gateway.id)));
+        }
+        self.record_pop(selection);
+        Ok(())
+    }
+    
+    fn record_pop(&self, selection: &gateway::PopSelection) {
+        {
+            let mut status = self.status.write();
+            status.server_ip = Some(selection.gateway.endpoint());
+            status.pop_id = Some(selection.gateway.id.clone());
+            status.latency_ms = selection.probe.latency_ms;
+        }
+        self.emit_event(ClientEvent::PopSelected {
+            pop_id: selection.gateway.id.clone(),
+            name: selection.gateway.name.clone(),
+            latency_ms: selection.probe.latency_ms,
+            reason: selection.reason,
+        });
+    }
+    
+    async fn sync_split_routes(&self) -> Result<(), ClientError> {
+        let report = self.routes.sync(&self.split_tunnel.planned_routes()).await?;
+        if report.failed > 0 {
+            tracing::warn!("{} split tunnel routes could not be programmed", report.failed);
+        }
+        Ok(())
+    }
+    
     /// Force posture re-check
     pub async fn refresh_posture(&self) -> posture::PostureResult {
         let result = self.posture.collect().await;
@@ -354,6 +766,16 @@ impl From<tunnel::TunnelError> for ClientError {
     }
 }
 
+impl From<split_tunnel::SplitTunnelError> for ClientError {
+    fn from(e: split_tunnel::SplitTunnelError) -> Self {
+        match e {
+            split_tunnel::SplitTunnelError::NoDefaultGateway
+            | split_tunnel::SplitTunnelError::RouteFailed(_) => ClientError::PlatformError(e.to_string()),
+            _ => ClientError::PolicyError(e.to_string()),
+        }
+    }
+}
+
 // =============================================================================
 // Platform Detection
 // =============================================================================
@@ -0,0 +1,291 @@
+//! Network Change Monitoring
+//!
+//! Watches the host's interfaces, default route and Wi-Fi SSID so the
+//! tunnel can re-handshake and re-select its gateway when the device
+//! roams, instead of waiting for keepalives to time out.
+
+use crate::split_tunnel::Gateway;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Point-in-time view of the host network
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetworkSnapshot {
+    /// Interfaces that are up, excluding loopback and the tunnel
+    pub interfaces: BTreeSet<String>,
+    pub default_route: Option<Gateway>,
+    pub ssid: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum NetworkChange {
+    InterfaceUp(String),
+    InterfaceDown(String),
+    /// Default route moved; `None` means the host is offline
+    DefaultRouteChanged {
+        previous: Option<String>,
+        current: Option<String>,
+    },
+    SsidChanged {
+        previous: Option<String>,
+        current: Option<String>,
+    },
+}
+
+impl NetworkChange {
+    /// Whether the tunnel's path to its gateway may have changed
+    pub fn triggers_roam(&self) -> bool {
+        matches!(
+            self,
+            NetworkChange::DefaultRouteChanged { current: Some(_), .. } | NetworkChange::SsidChanged { .. }
+        )
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self, NetworkChange::DefaultRouteChanged { current: None, .. })
+    }
+}
+
+fn describe_route(route: &Gateway) -> String {
+    match route.address {
+        Some(address) => format!("{} via {}", route.interface, address),
+        None => route.interface.clone(),
+    }
+}
+
+pub struct NetworkMonitor {
+    poll_interval: Duration,
+    last: tokio::sync::Mutex<Option<NetworkSnapshot>>,
+}
+
+impl NetworkMonitor {
+    pub fn new(poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            last: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Take a snapshot and report what changed since the previous one
+    ///
+    /// The first call only records a baseline.
+    pub async fn poll(&self) -> Vec<NetworkChange> {
+        let current = Self::snapshot().await;
+        let mut last = self.last.lock().await;
+        let changes = match last.as_ref() {
+            Some(previous) => Self::diff(previous, &current),
+            None => Vec::new(),
+        };
+        *last = Some(current);
+
+        for change in &changes {
+            tracing::info!("Network change: {:?}", change);
+        }
+        changes
+    }
+
+    /// Most recent snapshot, if any
+    pub async fn current(&self) -> Option<NetworkSnapshot> {
+        self.last.lock().await.clone()
+    }
+
+    pub fn diff(previous: &NetworkSnapshot, current: &NetworkSnapshot) -> Vec<NetworkChange> {
+        let mut changes: Vec<NetworkChange> = current.interfaces
+            .difference(&previous.interfaces)
+            .map(|i| NetworkChange::InterfaceUp(i.clone()))
+            .chain(previous.interfaces
+                .difference(&current.interfaces)
+                .map(|i| NetworkChange::InterfaceDown(i.clone())))
+            .collect();
+
+        if previous.default_route != current.default_route {
+            changes.push(NetworkChange::DefaultRouteChanged {
+                previous: previous.default_route.as_ref().map(describe_route),
+                current: current.default_route.as_ref().map(describe_route),
+            });
+        }
+        if previous.ssid != current.ssid {
+            changes.push(NetworkChange::SsidChanged {
+                previous: previous.ssid.clone(),
+                current: current.ssid.clone(),
+            });
+        }
+        changes
+    }
+
+    pub async fn snapshot() -> NetworkSnapshot {
+        NetworkSnapshot {
+            interfaces: Self::up_interfaces().await,
+            default_route: crate::split_tunnel::default_gateway().await.ok(),
+            ssid: Self::current_ssid().await,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn up_interfaces() -> BTreeSet<String> {
+        let mut interfaces = BTreeSet::new();
+        let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+            return interfaces;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == "lo" || name == crate::split_tunnel::TUNNEL_INTERFACE {
+                continue;
+            }
+            let state = std::fs::read_to_string(entry.path().join("operstate")).unwrap_or_default();
+            if state.trim() == "up" {
+                interfaces.insert(name);
+            }
+        }
+        interfaces
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn current_ssid() -> Option<String> {
+        let output = tokio::process::Command::new("iwgetid")
+            .arg("-r")
+            .output()
+            .await
+            .ok()?;
+        let ssid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!ssid.is_empty()).then_some(ssid)
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn up_interfaces() -> BTreeSet<String> {
+        let output = tokio::process::Command::new("ifconfig")
+            .arg("-lu")
+            .output()
+            .await;
+        output
+            .map(|o| String::from_utf8_lossy(&o.stdout)
+                .split_whitespace()
+                .filter(|i| !i.starts_with("lo") && !i.starts_with("utun") && *i != crate::split_tunnel::TUNNEL_INTERFACE)
+                .map(|i| i.to_string())
+                .collect())
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn current_ssid() -> Option<String> {
+        // Current Wi-Fi Network: HotelGuest
+        let output = tokio::process::Command::new("networksetup")
+            .args(["-getairportnetwork", "en0"])
+            .output()
+            .await
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .split_once(": ")
+            .map(|(_, ssid)| ssid.trim().to_string())
+            .filter(|ssid| !ssid.is_empty())
+    }
+
+    #[cfg(target_os = "windows")]
+    async fn up_interfaces() -> BTreeSet<String> {
+        let output = tokio::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", "Get-NetAdapter | Where-Object Status -eq 'Up' | Select-Object -ExpandProperty Name"])
+            .output()
+            .await;
+        output
+            .map(|o| String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && *l != crate::split_tunnel::TUNNEL_INTERFACE)
+                .map(|l| l.to_string())
+                .collect())
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_os = "windows")]
+    async fn current_ssid() -> Option<String> {
+        //     SSID                   : HotelGuest
+        let output = tokio::process::Command::new("netsh")
+            .args(["wlan", "show", "interfaces"])
+            .output()
+            .await
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(key, _)| key.trim() == "SSID")
+            .map(|(_, ssid)| ssid.trim().to_string())
+            .filter(|ssid| !ssid.is_empty())
+    }
+
+    // Mobile platforms report network changes to the VPN service, which
+    // forwards them through FFI
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    async fn up_interfaces() -> BTreeSet<String> {
+        BTreeSet::new()
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    async fn current_ssid() -> Option<String> {
+        None
+    }
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self { Self::new(Duration::from_secs(2)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(interfaces: &[&str], route: Option<(&str, &str)>, ssid: Option<&str>) -> NetworkSnapshot {
+        NetworkSnapshot {
+            interfaces: interfaces.iter().map(|i| i.to_string()).collect(),
+            default_route: route.map(|(interface, address)| Gateway {
+                address: Some(address.parse().unwrap()),
+                interface: interface.to_string(),
+            }),
+            ssid: ssid.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_each_change() {
+        let office = snapshot(&["eth0", "wlan0"], Some(("eth0", "10.0.0.1")), Some("Corp"));
+        assert!(NetworkMonitor::diff(&office, &office).is_empty());
+
+        let hotel = snapshot(&["wlan0", "usb0"], Some(("wlan0", "192.168.4.1")), Some("HotelGuest"));
+        assert_eq!(NetworkMonitor::diff(&office, &hotel), vec![
+            NetworkChange::InterfaceUp("usb0".to_string()),
+            NetworkChange::InterfaceDown("eth0".to_string()),
+            NetworkChange::DefaultRouteChanged {
+                previous: Some("eth0 via 10.0.0.1".to_string()),
+                current: Some("wlan0 via 192.168.4.1".to_string()),
+            },
+            NetworkChange::SsidChanged {
+                previous: Some("Corp".to_string()),
+                current: Some("HotelGuest".to_string()),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_roaming_and_offline_changes() {
+        let online = snapshot(&["eth0"], Some(("eth0", "10.0.0.1")), None);
+        let offline = snapshot(&[], None, None);
+
+        let down = NetworkMonitor::diff(&online, &offline);
+        assert!(down.iter().any(|c| c.is_offline()));
+        assert!(!down.iter().any(|c| c.triggers_roam()));
+
+        let up = NetworkMonitor::diff(&offline, &online);
+        assert!(up.iter().any(|c| c.triggers_roam()));
+        assert!(!up.iter().any(|c| c.is_offline()));
+
+        // Joining Wi-Fi behind the same router still re-handshakes
+        let wifi = snapshot(&["eth0"], Some(("eth0", "10.0.0.1")), Some("Corp"));
+        assert_eq!(NetworkMonitor::diff(&online, &wifi).len(), 1);
+        assert!(NetworkMonitor::diff(&online, &wifi)[0].triggers_roam());
+        assert!(!NetworkChange::InterfaceUp("usb0".to_string()).triggers_roam());
+    }
+}
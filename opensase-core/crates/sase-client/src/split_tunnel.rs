@@ -0,0 +1,1070 @@
+//! Split Tunnel Rules Engine
+//!
+//! Decides per flow whether traffic goes through the tunnel or straight
+//! out the local network. Rules include or exclude traffic by destination
+//! CIDR, domain suffix or application identity, and are evaluated in
+//! priority order with the first match winning.
+//!
+//! CIDR rules become routes on the host. Domain rules steer DNS: a domain
+//! that is tunneled resolves through the tunnel's resolvers, an excluded
+//! one through the local resolvers, and the addresses it resolves to are
+//! learned so packets to them follow the same rule. App rules have no
+//! route form and are enforced where flows are classified.
+//!
+//! The controller can push a new rule set at any time; rule sets carry a
+//! version and older ones are refused. Every rule keeps traffic counters
+//! that are reported through telemetry.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Tunnel interface that included routes point at
+pub const TUNNEL_INTERFACE: &str = "opensase0";
+
+/// Counter key for traffic that matched no rule
+pub const DEFAULT_RULE_ID: &str = "default";
+
+// =============================================================================
+// Configuration Schema
+// =============================================================================
+
+/// Split tunnel rule set, as configured locally or pushed by the controller
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SplitTunnelConfig {
+    /// Controller revision; 0 for local configuration
+    #[serde(default)]
+    pub version: u64,
+    /// What happens to traffic no rule matches
+    pub default_action: SplitAction,
+    #[serde(default)]
+    pub rules: Vec<SplitTunnelRule>,
+}
+
+impl Default for SplitTunnelConfig {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            default_action: SplitAction::Include,
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SplitTunnelRule {
+    pub id: String,
+    pub action: SplitAction,
+    #[serde(rename = "match")]
+    pub matcher: RuleMatch,
+    /// Lower values are evaluated first
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitAction {
+    /// Send through the tunnel
+    Include,
+    /// Bypass the tunnel
+    Exclude,
+}
+
+impl SplitAction {
+    pub fn tunnels(self) -> bool {
+        self == SplitAction::Include
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum RuleMatch {
+    /// Destination network, e.g. `10.0.0.0/8` or `2001:db8::/32`
+    Cidr(String),
+    /// Domain and all of its subdomains, e.g. `corp.example.com`
+    DomainSuffix(String),
+    /// Originating application
+    App(AppIdentity),
+}
+
+/// Application identity; every field that is set must match
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AppIdentity {
+    /// Process name, e.g. `zoom.exe`
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Full executable path
+    #[serde(default)]
+    pub path: Option<String>,
+    /// macOS/iOS bundle identifier or Android package name
+    #[serde(default)]
+    pub bundle_id: Option<String>,
+    /// Code-signing identity (Team ID, Authenticode subject)
+    #[serde(default)]
+    pub signer: Option<String>,
+}
+
+impl AppIdentity {
+    fn is_empty(&self) -> bool {
+        self.name.is_none() && self.path.is_none() && self.bundle_id.is_none() && self.signer.is_none()
+    }
+
+    fn matches(&self, app: &AppIdentity) -> bool {
+        fn field(want: &Option<String>, have: &Option<String>) -> bool {
+            match (want, have) {
+                (None, _) => true,
+                (Some(want), Some(have)) => want.eq_ignore_ascii_case(have),
+                (Some(_), None) => false,
+            }
+        }
+        field(&self.name, &app.name)
+            && field(&self.path, &app.path)
+            && field(&self.bundle_id, &app.bundle_id)
+            && field(&self.signer, &app.signer)
+    }
+}
+
+impl SplitTunnelConfig {
+    /// Build a rule set from the legacy split tunnel policies
+    pub fn from_policies(policies: &[crate::policy::Policy]) -> Option<Self> {
+        use crate::policy::{PolicyType, SplitTunnelMode};
+
+        let mut config: Option<Self> = None;
+        for policy in policies.iter().filter(|p| p.enabled) {
+            let PolicyType::SplitTunnel(st) = &policy.policy_type else { continue };
+            // Include mode tunnels the listed items and bypasses the rest
+            let (default_action, action) = match st.mode {
+                SplitTunnelMode::Include => (SplitAction::Exclude, SplitAction::Include),
+                SplitTunnelMode::Exclude => (SplitAction::Include, SplitAction::Exclude),
+            };
+            let config = config.get_or_insert_with(|| Self { default_action, ..Default::default() });
+
+            let matchers = st.ip_ranges.iter().cloned().map(RuleMatch::Cidr)
+                .chain(st.domains.iter().cloned().map(RuleMatch::DomainSuffix))
+                .chain(st.apps.iter().map(|app| RuleMatch::App(AppIdentity {
+                    name: Some(app.clone()),
+                    ..Default::default()
+                })));
+            for (i, matcher) in matchers.enumerate() {
+                config.rules.push(SplitTunnelRule {
+                    id: format!("{}-{}", policy.id, i),
+                    action,
+                    matcher,
+                    priority: policy.priority,
+                    enabled: true,
+                });
+            }
+        }
+        config
+    }
+}
+
+// =============================================================================
+// CIDR
+// =============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    /// Network address, host bits cleared
+    pub network: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl Cidr {
+    /// Single-address network
+    pub fn host(ip: IpAddr) -> Self {
+        let prefix_len = if ip.is_ipv4() { 32 } else { 128 };
+        Self { network: ip, prefix_len }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(ip) & mask == u32::from(net)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(ip) & mask == u128::from(net)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = SplitTunnelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SplitTunnelError::InvalidCidr(s.to_string());
+        let (addr, prefix_len) = match s.trim().split_once('/') {
+            Some((addr, len)) => (addr, Some(len.parse::<u8>().map_err(|_| invalid())?)),
+            None => (s.trim(), None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max);
+        if prefix_len > max {
+            return Err(invalid());
+        }
+        // Clear host bits so 10.1.2.3/8 and 10.0.0.0/8 are the same route
+        let network = match addr {
+            IpAddr::V4(v4) => {
+                let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+                IpAddr::V4((u32::from(v4) & mask).into())
+            }
+            IpAddr::V6(v6) => {
+                let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+                IpAddr::V6((u128::from(v6) & mask).into())
+            }
+        };
+        Ok(Self { network, prefix_len })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Lowercase, no wildcard prefix, no trailing dot
+fn normalize_domain(domain: &str) -> String {
+    domain.trim()
+        .trim_start_matches("*.")
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+fn domain_matches(domain: &str, suffix: &str) -> bool {
+    domain == suffix
+        || (domain.len() > suffix.len()
+            && domain.ends_with(suffix)
+            && domain.as_bytes()[domain.len() - suffix.len() - 1] == b'.')
+}
+
+// =============================================================================
+// Engine
+// =============================================================================
+
+#[derive(Clone, Debug)]
+enum CompiledMatch {
+    Cidr(Cidr),
+    Domain(String),
+    App(AppIdentity),
+}
+
+#[derive(Clone, Debug)]
+struct CompiledRule {
+    id: Arc<str>,
+    action: SplitAction,
+    matcher: CompiledMatch,
+}
+
+#[derive(Debug)]
+struct RuleSet {
+    version: u64,
+    default_action: SplitAction,
+    /// Enabled rules in evaluation order
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    fn compile(config: &SplitTunnelConfig) -> Result<Self, SplitTunnelError> {
+        let mut seen = HashSet::new();
+        let mut ordered: Vec<&SplitTunnelRule> = config.rules.iter().filter(|r| r.enabled).collect();
+        // Stable sort keeps the controller's order among equal priorities
+        ordered.sort_by_key(|r| r.priority);
+
+        let mut rules = Vec::with_capacity(ordered.len());
+        for rule in ordered {
+            if rule.id.is_empty() || rule.id == DEFAULT_RULE_ID || !seen.insert(rule.id.as_str()) {
+                return Err(SplitTunnelError::InvalidRule(format!("duplicate or reserved id '{}'", rule.id)));
+            }
+            let matcher = match &rule.matcher {
+                RuleMatch::Cidr(cidr) => CompiledMatch::Cidr(cidr.parse()?),
+                RuleMatch::DomainSuffix(domain) => {
+                    let domain = normalize_domain(domain);
+                    if domain.is_empty() {
+                        return Err(SplitTunnelError::InvalidRule(format!("rule '{}' has an empty domain", rule.id)));
+                    }
+                    CompiledMatch::Domain(domain)
+                }
+                RuleMatch::App(app) => {
+                    if app.is_empty() {
+                        return Err(SplitTunnelError::InvalidRule(format!("rule '{}' identifies no application", rule.id)));
+                    }
+                    CompiledMatch::App(app.clone())
+                }
+            };
+            rules.push(CompiledRule { id: rule.id.as_str().into(), action: rule.action, matcher });
+        }
+
+        Ok(Self { version: config.version, default_action: config.default_action, rules })
+    }
+}
+
+/// What is known about a flow when it is classified
+#[derive(Clone, Debug, Default)]
+pub struct FlowContext<'a> {
+    pub app: Option<&'a AppIdentity>,
+    pub domain: Option<&'a str>,
+    pub destination: Option<IpAddr>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SplitDecision {
+    pub action: SplitAction,
+    /// Matching rule; `None` when the default action applied
+    pub rule_id: Option<Arc<str>>,
+}
+
+impl SplitDecision {
+    pub fn tunnel(&self) -> bool {
+        self.action.tunnels()
+    }
+}
+
+/// Which resolvers answer a name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DnsRoute {
+    Tunnel,
+    Local,
+}
+
+/// Per-domain resolver selection for the DNS manager
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DnsPlan {
+    /// Resolvers for names no domain rule covers
+    pub default_tunnel: bool,
+    pub tunnel_domains: Vec<String>,
+    pub local_domains: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum RouteTarget {
+    /// Through the tunnel interface
+    Tunnel,
+    /// Through the local default gateway
+    Local,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlannedRoute {
+    pub destination: Cidr,
+    pub target: RouteTarget,
+}
+
+#[derive(Default)]
+struct RuleCounters {
+    flows: AtomicU64,
+    packets: AtomicU64,
+    bytes: AtomicU64,
+}
+
+struct LearnedAddress {
+    rule_id: Arc<str>,
+    action: SplitAction,
+    expires: Instant,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuleTelemetry {
+    pub rule_id: String,
+    pub action: Option<SplitAction>,
+    pub flows: u64,
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// Counter report sent to the controller
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SplitTunnelTelemetry {
+    pub version: u64,
+    pub collected_at: DateTime<Utc>,
+    /// Counters are cumulative from this point
+    pub since: DateTime<Utc>,
+    pub rules: Vec<RuleTelemetry>,
+}
+
+pub struct SplitTunnelEngine {
+    rules: parking_lot::RwLock<Arc<RuleSet>>,
+    counters: DashMap<Arc<str>, Arc<RuleCounters>>,
+    learned: DashMap<IpAddr, LearnedAddress>,
+    since: DateTime<Utc>,
+}
+
+impl SplitTunnelEngine {
+    pub fn new() -> Self {
+        let rules = RuleSet::compile(&SplitTunnelConfig::default()).expect("empty rule set compiles");
+        let counters = DashMap::new();
+        counters.insert(Arc::from(DEFAULT_RULE_ID), Arc::new(RuleCounters::default()));
+        Self {
+            rules: parking_lot::RwLock::new(Arc::new(rules)),
+            counters,
+            learned: DashMap::new(),
+            since: Utc::now(),
+        }
+    }
+
+    /// Replace the rule set; false if this version is already applied
+    ///
+    /// Controller revisions older than the current one are refused so a
+    /// delayed push can't roll the rules back. Counters of rules that
+    /// survive the update are kept.
+    pub fn apply(&self, config: &SplitTunnelConfig) -> Result<bool, SplitTunnelError> {
+        let compiled = RuleSet::compile(config)?;
+        let mut current = self.rules.write();
+        if config.version != 0 {
+            if config.version < current.version {
+                return Err(SplitTunnelError::StaleUpdate { current: current.version, received: config.version });
+            }
+            if config.version == current.version {
+                return Ok(false);
+            }
+        }
+
+        let ids: HashSet<&str> = compiled.rules.iter().map(|r| &*r.id).collect();
+        self.counters.retain(|id, _| &**id == DEFAULT_RULE_ID || ids.contains(&**id));
+        for rule in &compiled.rules {
+            self.counters.entry(rule.id.clone()).or_default();
+        }
+        // Learned addresses may belong to rules that changed meaning
+        self.learned.clear();
+
+        tracing::info!(
+            "Applied split tunnel rules v{} ({} rules, default {:?})",
+            compiled.version, compiled.rules.len(), compiled.default_action
+        );
+        *current = Arc::new(compiled);
+        Ok(true)
+    }
+
+    pub fn version(&self) -> u64 {
+        self.rules.read().version
+    }
+
+    /// Classify a flow
+    pub fn decide(&self, flow: &FlowContext<'_>) -> SplitDecision {
+        let rules = self.rules.read().clone();
+        let domain = flow.domain.map(normalize_domain);
+        let learned = flow.destination.and_then(|ip| {
+            self.learned.get(&ip)
+                .filter(|l| l.expires > Instant::now())
+                .map(|l| l.rule_id.clone())
+        });
+
+        for rule in &rules.rules {
+            let hit = match &rule.matcher {
+                CompiledMatch::Cidr(cidr) => flow.destination.is_some_and(|ip| cidr.contains(ip)),
+                CompiledMatch::Domain(suffix) => {
+                    domain.as_deref().is_some_and(|d| domain_matches(d, suffix))
+                        || learned.as_deref() == Some(&*rule.id)
+                }
+                CompiledMatch::App(identity) => flow.app.is_some_and(|app| identity.matches(app)),
+            };
+            if hit {
+                return SplitDecision { action: rule.action, rule_id: Some(rule.id.clone()) };
+            }
+        }
+        SplitDecision { action: rules.default_action, rule_id: None }
+    }
+
+    /// Count a new flow against the rule that decided it
+    pub fn record_flow(&self, decision: &SplitDecision) {
+        if let Some(counters) = self.counters_for(decision) {
+            counters.flows.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count traffic against the rule that decided it
+    pub fn record(&self, decision: &SplitDecision, packets: u64, bytes: u64) {
+        if let Some(counters) = self.counters_for(decision) {
+            counters.packets.fetch_add(packets, Ordering::Relaxed);
+            counters.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    fn counters_for(&self, decision: &SplitDecision) -> Option<Arc<RuleCounters>> {
+        let id = decision.rule_id.as_deref().unwrap_or(DEFAULT_RULE_ID);
+        // A rule removed since the decision was made is not counted
+        self.counters.get(id).map(|c| c.clone())
+    }
+
+    /// Resolvers that should answer `domain`
+    pub fn dns_route(&self, domain: &str) -> DnsRoute {
+        let rules = self.rules.read().clone();
+        let domain = normalize_domain(domain);
+        let action = rules.rules.iter()
+            .find_map(|rule| match &rule.matcher {
+                CompiledMatch::Domain(suffix) if domain_matches(&domain, suffix) => Some(rule.action),
+                _ => None,
+            })
+            .unwrap_or(rules.default_action);
+        if action.tunnels() { DnsRoute::Tunnel } else { DnsRoute::Local }
+    }
+
+    /// Domains that need a non-default resolver
+    pub fn dns_plan(&self) -> DnsPlan {
+        let rules = self.rules.read().clone();
+        let default_tunnel = rules.default_action.tunnels();
+        let mut plan = DnsPlan { default_tunnel, ..Default::default() };
+        for rule in &rules.rules {
+            if let CompiledMatch::Domain(suffix) = &rule.matcher {
+                let list = if rule.action.tunnels() { &mut plan.tunnel_domains } else { &mut plan.local_domains };
+                if !list.contains(suffix) {
+                    list.push(suffix.clone());
+                }
+            }
+        }
+        plan
+    }
+
+    /// Bind the addresses a domain resolved to its domain rule
+    ///
+    /// Returns the action for the addresses when a domain rule matched,
+    /// in which case the caller should resync routes.
+    pub fn learn_resolution(&self, domain: &str, addresses: &[IpAddr], ttl: Duration) -> Option<SplitAction> {
+        let rules = self.rules.read().clone();
+        let domain = normalize_domain(domain);
+        let rule = rules.rules.iter().find(|rule| match &rule.matcher {
+            CompiledMatch::Domain(suffix) => domain_matches(&domain, suffix),
+            _ => false,
+        })?;
+        // Routes for an address outlive a single short TTL
+        let expires = Instant::now() + ttl.max(Duration::from_secs(60));
+        for ip in addresses {
+            self.learned.insert(*ip, LearnedAddress { rule_id: rule.id.clone(), action: rule.action, expires });
+        }
+        Some(rule.action)
+    }
+
+    /// Routes the host should carry for the current rules
+    ///
+    /// Only rules whose action differs from the default need a route;
+    /// the default itself is carried by the tunnel's own routes.
+    pub fn planned_routes(&self) -> Vec<PlannedRoute> {
+        let rules = self.rules.read().clone();
+        let target = |action: SplitAction| if action.tunnels() { RouteTarget::Tunnel } else { RouteTarget::Local };
+
+        let mut routes: Vec<PlannedRoute> = rules.rules.iter()
+            .filter_map(|rule| match rule.matcher {
+                CompiledMatch::Cidr(cidr) => Some(PlannedRoute { destination: cidr, target: target(rule.action) }),
+                _ => None,
+            })
+            .collect();
+
+        let now = Instant::now();
+        self.learned.retain(|_, l| l.expires > now);
+        routes.extend(self.learned.iter()
+            .filter(|l| l.action != rules.default_action)
+            .map(|l| PlannedRoute { destination: Cidr::host(*l.key()), target: target(l.action) }));
+
+        // A more specific opposite-action rule may sit under a broader one,
+        // so every CIDR rule gets a route, but duplicates are dropped
+        let mut seen = HashSet::new();
+        routes.retain(|r| seen.insert(r.destination));
+        routes
+    }
+
+    /// Current counters for telemetry
+    pub fn telemetry(&self) -> SplitTunnelTelemetry {
+        let rules = self.rules.read().clone();
+        let snapshot = |id: &str, action: Option<SplitAction>| {
+            let counters = self.counters.get(id).map(|c| c.clone()).unwrap_or_default();
+            RuleTelemetry {
+                rule_id: id.to_string(),
+                action,
+                flows: counters.flows.load(Ordering::Relaxed),
+                packets: counters.packets.load(Ordering::Relaxed),
+                bytes: counters.bytes.load(Ordering::Relaxed),
+            }
+        };
+
+        let mut report: Vec<RuleTelemetry> = rules.rules.iter()
+            .map(|rule| snapshot(&rule.id, Some(rule.action)))
+            .collect();
+        report.push(snapshot(DEFAULT_RULE_ID, None));
+
+        SplitTunnelTelemetry {
+            version: rules.version,
+            collected_at: Utc::now(),
+            since: self.since,
+            rules: report,
+        }
+    }
+
+}
+
+impl Default for SplitTunnelEngine {
+    fn default() -> Self { Self::new() }
+}
+
+// =============================================================================
+// Route Programming
+// =============================================================================
+
+/// Keeps the host routing table in line with the planned routes
+pub struct RouteProgrammer {
+    installed: tokio::sync::Mutex<HashSet<PlannedRoute>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RouteSyncReport {
+    pub added: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+impl RouteProgrammer {
+    pub fn new() -> Self {
+        Self { installed: tokio::sync::Mutex::new(HashSet::new()) }
+    }
+
+    /// Add missing routes and remove ones no longer planned
+    pub async fn sync(&self, planned: &[PlannedRoute]) -> Result<RouteSyncReport, SplitTunnelError> {
+        let mut installed = self.installed.lock().await;
+        let wanted: HashSet<PlannedRoute> = planned.iter().copied().collect();
+        let gateway = if wanted.iter().any(|r| r.target == RouteTarget::Local) {
+            Some(default_gateway().await?)
+        } else {
+            None
+        };
+
+        let mut report = RouteSyncReport::default();
+        let stale: Vec<PlannedRoute> = installed.difference(&wanted).copied().collect();
+        for route in stale {
+            match delete_route(&route).await {
+                Ok(()) => report.removed += 1,
+                Err(e) => {
+                    tracing::warn!("Failed to remove route {}: {}", route.destination, e);
+                    report.failed += 1;
+                }
+            }
+            installed.remove(&route);
+        }
+
+        let missing: Vec<PlannedRoute> = wanted.difference(&installed).copied().collect();
+        for route in missing {
+            match add_route(&route, gateway.as_ref()).await {
+                Ok(()) => {
+                    installed.insert(route);
+                    report.added += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to add route {}: {}", route.destination, e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        tracing::debug!("Split tunnel routes synced: {:?}", report);
+        Ok(report)
+    }
+
+    /// Remove every route this programmer added
+    pub async fn clear(&self) -> Result<(), SplitTunnelError> {
+        self.sync(&[]).await.map(|_| ())
+    }
+}
+
+impl Default for RouteProgrammer {
+    fn default() -> Self { Self::new() }
+}
+
+/// Local next hop for excluded traffic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gateway {
+    pub address: Option<IpAddr>,
+    pub interface: String,
+}
+
+async fn run(program: &str, args: &[&str]) -> Result<String, SplitTunnelError> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| SplitTunnelError::RouteFailed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(SplitTunnelError::RouteFailed(format!(
+            "{} {}: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) async fn default_gateway() -> Result<Gateway, SplitTunnelError> {
+    // default via 192.168.1.1 dev wlan0 proto dhcp metric 600
+    let output = run("ip", &["route", "show", "default"]).await?;
+    let fields: Vec<&str> = output.lines()
+        .find(|l| !l.contains(TUNNEL_INTERFACE))
+        .map(|l| l.split_whitespace().collect())
+        .unwrap_or_default();
+    let after = |key: &str| fields.iter().position(|f| *f == key).and_then(|i| fields.get(i + 1));
+    let interface = after("dev").ok_or(SplitTunnelError::NoDefaultGateway)?.to_string();
+    Ok(Gateway { address: after("via").and_then(|a| a.parse().ok()), interface })
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) async fn add_route(route: &PlannedRoute, gateway: Option<&Gateway>) -> Result<(), SplitTunnelError> {
+    let destination = route.destination.to_string();
+    let family = if route.destination.network.is_ipv4() { "-4" } else { "-6" };
+    match (route.target, gateway) {
+        (RouteTarget::Tunnel, _) => {
+            run("ip", &[family, "route", "replace", &destination, "dev", TUNNEL_INTERFACE]).await?;
+        }
+        (RouteTarget::Local, Some(gw)) => {
+            let mut args = vec![family, "route", "replace", destination.as_str()];
+            let via = gw.address.filter(|a| a.is_ipv4() == route.destination.network.is_ipv4()).map(|a| a.to_string());
+            if let Some(via) = via.as_deref() {
+                args.extend(["via", via]);
+            }
+            args.extend(["dev", gw.interface.as_str()]);
+            run("ip", &args).await?;
+        }
+        (RouteTarget::Local, None) => return Err(SplitTunnelError::NoDefaultGateway),
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) async fn delete_route(route: &PlannedRoute) -> Result<(), SplitTunnelError> {
+    let family = if route.destination.network.is_ipv4() { "-4" } else { "-6" };
+    run("ip", &[family, "route", "del", &route.destination.to_string()]).await.map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) async fn default_gateway() -> Result<Gateway, SplitTunnelError> {
+    //    gateway: 192.168.1.1
+    //  interface: en0
+    let output = run("route", &["-n", "get", "default"]).await?;
+    let field = |key: &str| output.lines()
+        .filter_map(|l| l.trim().split_once(": "))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v.trim().to_string());
+    let interface = field("interface").ok_or(SplitTunnelError::NoDefaultGateway)?;
+    Ok(Gateway { address: field("gateway").and_then(|a| a.parse().ok()), interface })
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) async fn add_route(route: &PlannedRoute, gateway: Option<&Gateway>) -> Result<(), SplitTunnelError> {
+    let destination = route.destination.to_string();
+    let family = if route.destination.network.is_ipv4() { "-inet" } else { "-inet6" };
+    match (route.target, gateway) {
+        (RouteTarget::Tunnel, _) => {
+            run("route", &["-n", "add", family, "-net", &destination, "-interface", TUNNEL_INTERFACE]).await?;
+        }
+        (RouteTarget::Local, Some(Gateway { address: Some(via), .. })) => {
+            run("route", &["-n", "add", family, "-net", &destination, &via.to_string()]).await?;
+        }
+        (RouteTarget::Local, Some(gw)) => {
+            run("route", &["-n", "add", family, "-net", &destination, "-interface", &gw.interface]).await?;
+        }
+        (RouteTarget::Local, None) => return Err(SplitTunnelError::NoDefaultGateway),
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) async fn delete_route(route: &PlannedRoute) -> Result<(), SplitTunnelError> {
+    let family = if route.destination.network.is_ipv4() { "-inet" } else { "-inet6" };
+    run("route", &["-n", "delete", family, "-net", &route.destination.to_string()]).await.map(|_| ())
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) async fn default_gateway() -> Result<Gateway, SplitTunnelError> {
+    let output = run("powershell", &[
+        "-NoProfile", "-Command",
+        "$r = Get-NetRoute -DestinationPrefix 0.0.0.0/0 | Sort-Object RouteMetric | Select-Object -First 1; \
+         \"$($r.NextHop) $($r.InterfaceIndex)\"",
+    ]).await?;
+    let mut fields = output.split_whitespace();
+    let address = fields.next().and_then(|a| a.parse().ok());
+    let interface = fields.next().ok_or(SplitTunnelError::NoDefaultGateway)?.to_string();
+    Ok(Gateway { address, interface })
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) async fn add_route(route: &PlannedRoute, gateway: Option<&Gateway>) -> Result<(), SplitTunnelError> {
+    let family = if route.destination.network.is_ipv4() { "ipv4" } else { "ipv6" };
+    let prefix = format!("prefix={}", route.destination);
+    match (route.target, gateway) {
+        (RouteTarget::Tunnel, _) => {
+            let interface = format!("interface={}", TUNNEL_INTERFACE);
+            run("netsh", &["interface", family, "add", "route", &prefix, &interface, "store=active"]).await?;
+        }
+        (RouteTarget::Local, Some(gw)) => {
+            let interface = format!("interface={}", gw.interface);
+            let mut args = vec!["interface", family, "add", "route", prefix.as_str(), interface.as_str()];
+            let nexthop = gw.address.map(|a| format!("nexthop={}", a));
+            if let Some(nexthop) = nexthop.as_deref() {
+                args.push(nexthop);
+            }
+            args.push("store=active");
+            run("netsh", &args).await?;
+        }
+        (RouteTarget::Local, None) => return Err(SplitTunnelError::NoDefaultGateway),
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) async fn delete_route(route: &PlannedRoute) -> Result<(), SplitTunnelError> {
+    let family = if route.destination.network.is_ipv4() { "ipv4" } else { "ipv6" };
+    let prefix = format!("prefix={}", route.destination);
+    run("netsh", &["interface", family, "delete", "route", &prefix, "store=active"]).await.map(|_| ())
+}
+
+// Mobile platforms take routes from the VPN service configuration
+// handed over through FFI, so nothing is programmed here
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub(crate) async fn default_gateway() -> Result<Gateway, SplitTunnelError> {
+    Ok(Gateway { address: None, interface: String::new() })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub(crate) async fn add_route(_route: &PlannedRoute, _gateway: Option<&Gateway>) -> Result<(), SplitTunnelError> {
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub(crate) async fn delete_route(_route: &PlannedRoute) -> Result<(), SplitTunnelError> {
+    Ok(())
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug, thiserror::Error)]
+pub enum SplitTunnelError {
+    #[error("Invalid CIDR: {0}")]
+    InvalidCidr(String),
+
+    #[error("Invalid rule: {0}")]
+    InvalidRule(String),
+
+    #[error("Stale rule update: have v{current}, received v{received}")]
+    StaleUpdate { current: u64, received: u64 },
+
+    #[error("No default gateway for excluded routes")]
+    NoDefaultGateway,
+
+    #[error("Route programming failed: {0}")]
+    RouteFailed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Corporate network and domain tunneled, Zoom and one subnet excluded,
+    /// everything else local
+    const RULES: &str = r#"{
+        "version": 3,
+        "default_action": "exclude",
+        "rules": [
+            { "id": "zoom", "action": "exclude", "match": { "type": "app", "value": { "name": "Zoom.exe" } }, "priority": 1 },
+            { "id": "printers", "action": "exclude", "match": { "type": "cidr", "value": "10.9.0.0/16" }, "priority": 5 },
+            { "id": "corp-net", "action": "include", "match": { "type": "cidr", "value": "10.1.2.3/8" }, "priority": 10 },
+            { "id": "corp-dns", "action": "include", "match": { "type": "domain_suffix", "value": "*.Corp.Example.com." }, "priority": 10 },
+            { "id": "off", "action": "include", "match": { "type": "cidr", "value": "0.0.0.0/0" }, "enabled": false }
+        ]
+    }"#;
+
+    fn engine() -> SplitTunnelEngine {
+        let engine = SplitTunnelEngine::new();
+        assert!(engine.apply(&serde_json::from_str(RULES).unwrap()).unwrap());
+        engine
+    }
+
+    fn decide(engine: &SplitTunnelEngine, flow: FlowContext<'_>) -> (SplitAction, Option<String>) {
+        let decision = engine.decide(&flow);
+        (decision.action, decision.rule_id.map(|id| id.to_string()))
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_cidr_parsing() {
+        let cidr: Cidr = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(cidr.to_string(), "10.0.0.0/8");
+        assert!(cidr.contains("10.255.0.1".parse().unwrap()));
+        assert!(!cidr.contains("11.0.0.1".parse().unwrap()));
+        assert!(!cidr.contains("::ffff:10.0.0.1".parse().unwrap()));
+
+        let v6: Cidr = "2001:db8::1/32".parse().unwrap();
+        assert_eq!(v6.to_string(), "2001:db8::/32");
+        assert_eq!("192.0.2.7".parse::<Cidr>().unwrap().prefix_len, 32);
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains("203.0.113.9".parse().unwrap()));
+
+        for bad in ["10.0.0.0/33", "10.0.0/8", "10.0.0.0/x", "::/129"] {
+            assert!(matches!(bad.parse::<Cidr>(), Err(SplitTunnelError::InvalidCidr(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let engine = engine();
+        let zoom = AppIdentity { name: Some("zoom.EXE".to_string()), ..Default::default() };
+
+        // App rule has the highest priority, even for tunneled addresses
+        let flow = FlowContext { app: Some(&zoom), destination: ip("10.1.1.1"), ..Default::default() };
+        assert_eq!(decide(&engine, flow), (SplitAction::Exclude, Some("zoom".to_string())));
+        // More specific exclusion listed before the broader inclusion
+        let flow = FlowContext { destination: ip("10.9.4.4"), ..Default::default() };
+        assert_eq!(decide(&engine, flow).1.as_deref(), Some("printers"));
+        let flow = FlowContext { destination: ip("10.200.0.1"), ..Default::default() };
+        assert_eq!(decide(&engine, flow), (SplitAction::Include, Some("corp-net".to_string())));
+        // Domain suffixes match subdomains only at a label boundary
+        let flow = FlowContext { domain: Some("git.corp.example.com"), ..Default::default() };
+        assert_eq!(decide(&engine, flow).1.as_deref(), Some("corp-dns"));
+        let flow = FlowContext { domain: Some("notcorp.example.com"), destination: ip("198.51.100.1"), ..Default::default() };
+        assert_eq!(decide(&engine, flow), (SplitAction::Exclude, None));
+    }
+
+    #[test]
+    fn test_rule_set_updates() {
+        let engine = engine();
+        let mut config: SplitTunnelConfig = serde_json::from_str(RULES).unwrap();
+
+        assert!(!engine.apply(&config).unwrap(), "same version is a no-op");
+        config.version = 2;
+        assert!(matches!(
+            engine.apply(&config),
+            Err(SplitTunnelError::StaleUpdate { current: 3, received: 2 })
+        ));
+
+        config.version = 4;
+        config.rules[1].id = "zoom".to_string();
+        assert!(matches!(engine.apply(&config), Err(SplitTunnelError::InvalidRule(_))));
+        config.rules[1].id = DEFAULT_RULE_ID.to_string();
+        assert!(matches!(engine.apply(&config), Err(SplitTunnelError::InvalidRule(_))));
+        config.rules[1].id = "printers".to_string();
+        config.rules[3].matcher = RuleMatch::DomainSuffix("*.".to_string());
+        assert!(matches!(engine.apply(&config), Err(SplitTunnelError::InvalidRule(_))));
+        config.rules[3].matcher = RuleMatch::App(AppIdentity::default());
+        assert!(matches!(engine.apply(&config), Err(SplitTunnelError::InvalidRule(_))));
+        assert_eq!(engine.version(), 3, "failed updates leave the rules alone");
+
+        // Local configuration (version 0) always applies
+        assert!(engine.apply(&SplitTunnelConfig::default()).unwrap());
+        assert_eq!(engine.version(), 0);
+    }
+
+    #[test]
+    fn test_dns_routing_and_learned_addresses() {
+        let engine = engine();
+        assert_eq!(engine.dns_route("WIKI.corp.example.com."), DnsRoute::Tunnel);
+        assert_eq!(engine.dns_route("example.com"), DnsRoute::Local);
+        assert_eq!(engine.dns_plan(), DnsPlan {
+            default_tunnel: false,
+            tunnel_domains: vec!["corp.example.com".to_string()],
+            local_domains: vec![],
+        });
+
+        // Addresses a tunneled name resolves to follow its rule
+        let address = ip("203.0.113.80").unwrap();
+        assert_eq!(engine.learn_resolution("example.org", &[address], Duration::from_secs(30)), None);
+        assert_eq!(
+            engine.learn_resolution("app.corp.example.com", &[address], Duration::from_secs(5)),
+            Some(SplitAction::Include)
+        );
+        let flow = FlowContext { destination: Some(address), ..Default::default() };
+        assert_eq!(decide(&engine, flow).1.as_deref(), Some("corp-dns"));
+
+        let routes = engine.planned_routes();
+        assert_eq!(routes.len(), 3);
+        assert!(routes.contains(&PlannedRoute { destination: "10.0.0.0/8".parse().unwrap(), target: RouteTarget::Tunnel }));
+        assert!(routes.contains(&PlannedRoute { destination: "10.9.0.0/16".parse().unwrap(), target: RouteTarget::Local }));
+        assert!(routes.contains(&PlannedRoute { destination: Cidr::host(address), target: RouteTarget::Tunnel }));
+
+        // A new rule set forgets learned addresses
+        let mut config: SplitTunnelConfig = serde_json::from_str(RULES).unwrap();
+        config.version = 4;
+        engine.apply(&config).unwrap();
+        assert_eq!(engine.planned_routes().len(), 2);
+    }
+
+    #[test]
+    fn test_counters_survive_updates_of_kept_rules() {
+        let engine = engine();
+        let tunneled = engine.decide(&FlowContext { destination: ip("10.0.0.1"), ..Default::default() });
+        let default = engine.decide(&FlowContext { destination: ip("198.51.100.1"), ..Default::default() });
+        engine.record_flow(&tunneled);
+        engine.record(&tunneled, 10, 1500);
+        engine.record(&default, 1, 60);
+        let printers = engine.decide(&FlowContext { destination: ip("10.9.0.1"), ..Default::default() });
+
+        let mut config: SplitTunnelConfig = serde_json::from_str(RULES).unwrap();
+        config.version = 4;
+        config.rules.retain(|r| r.id != "printers");
+        engine.apply(&config).unwrap();
+        // Decided before the rule was removed; not counted anywhere
+        engine.record(&printers, 5, 500);
+
+        let telemetry = engine.telemetry();
+        assert_eq!(telemetry.version, 4);
+        let counters = |id: &str| {
+            let rule = telemetry.rules.iter().find(|r| r.rule_id == id).unwrap();
+            (rule.flows, rule.packets, rule.bytes)
+        };
+        assert_eq!(counters("corp-net"), (1, 10, 1500));
+        assert_eq!(counters(DEFAULT_RULE_ID), (0, 1, 60));
+        // Disabled and removed rules report nothing
+        let ids: Vec<&str> = telemetry.rules.iter().map(|r| r.rule_id.as_str()).collect();
+        assert_eq!(ids, vec!["zoom", "corp-net", "corp-dns", DEFAULT_RULE_ID]);
+    }
+
+    #[test]
+    fn test_rules_from_legacy_policies() {
+        use crate::policy::{Policy, PolicyAction, PolicyType, SplitTunnelMode, SplitTunnelPolicy};
+
+        let policy = |id: &str, enabled: bool| Policy {
+            id: id.to_string(),
+            name: id.to_string(),
+            policy_type: PolicyType::SplitTunnel(SplitTunnelPolicy {
+                mode: SplitTunnelMode::Include,
+                apps: vec!["slack".to_string()],
+                domains: vec!["corp.example.com".to_string()],
+                ip_ranges: vec!["10.0.0.0/8".to_string()],
+            }),
+            action: PolicyAction::Allow,
+            priority: 7,
+            enabled,
+        };
+        assert!(SplitTunnelConfig::from_policies(&[policy("off", false)]).is_none());
+
+        let config = SplitTunnelConfig::from_policies(&[policy("corp", true)]).unwrap();
+        assert_eq!(config.default_action, SplitAction::Exclude);
+        let ids: Vec<&str> = config.rules.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["corp-0", "corp-1", "corp-2"]);
+        assert!(config.rules.iter().all(|r| r.action == SplitAction::Include && r.priority == 7));
+
+        let engine = SplitTunnelEngine::new();
+        engine.apply(&config).unwrap();
+        let slack = AppIdentity { name: Some("Slack".to_string()), ..Default::default() };
+        assert!(engine.decide(&FlowContext { app: Some(&slack), ..Default::default() }).tunnel());
+    }
+}
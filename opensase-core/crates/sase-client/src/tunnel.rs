@@ -2,13 +2,17 @@
 //!
 //! Cross-platform WireGuard tunnel management.
 
+use crate::captive::PassThroughWindow;
+use crate::split_tunnel::{Cidr, PlannedRoute, RouteTarget, TUNNEL_INTERFACE};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct TunnelManager {
     state: parking_lot::RwLock<TunnelState>,
     config: parking_lot::RwLock<Option<TunnelConfig>>,
     stats: parking_lot::RwLock<TunnelStats>,
+    pass_through: parking_lot::RwLock<Option<PassThroughWindow>>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -22,6 +26,9 @@ pub struct TunnelConfig {
     pub mtu: u16,
     pub keepalive: u16,
     pub policies: Vec<crate::policy::Policy>,
+    /// Split tunnel rules; legacy split tunnel policies apply when absent
+    #[serde(default)]
+    pub split_tunnel: Option<crate::split_tunnel::SplitTunnelConfig>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -30,6 +37,8 @@ pub enum TunnelState {
     Connecting,
     Connected,
     Reconnecting,
+    /// Tunnel routes suspended so a captive portal can be signed in to
+    PassThrough,
     Error,
 }
 
@@ -48,6 +57,7 @@ impl TunnelManager {
             state: parking_lot::RwLock::new(TunnelState::Disconnected),
             config: parking_lot::RwLock::new(None),
             stats: parking_lot::RwLock::new(TunnelStats::default()),
+            pass_through: parking_lot::RwLock::new(None),
         }
     }
     
@@ -95,6 +105,7 @@ impl TunnelManager {
         
         *self.state.write() = TunnelState::Disconnected;
         *self.config.write() = None;
+        *self.pass_through.write() = None;
         
         Ok(())
     }
@@ -107,6 +118,105 @@ impl TunnelManager {
         self.stats.read().clone()
     }
     
+    pub fn config(&self) -> Option<TunnelConfig> {
+        self.config.read().clone()
+    }
+    
+    // =========================================================================
+    // Roaming
+    // =========================================================================
+    
+    /// Force a fresh handshake on the current network path
+    ///
+    /// The interface and its address stay up, so sessions inside the
+    /// tunnel survive the change of underlying network.
+    pub async fn rehandshake(&self) -> Result<(), TunnelError> {
+        let config = self.config()
+            .ok_or_else(|| TunnelError::ConfigError("Tunnel not configured".to_string()))?;
+        tracing::info!("Re-handshaking with {}", config.server_endpoint);
+        set_peer(&config, None).await
+    }
+    
+    /// Move the tunnel to another gateway without tearing it down
+    pub async fn switch_endpoint(&self, endpoint: &str, public_key: &str) -> Result<(), TunnelError> {
+        let mut config = self.config()
+            .ok_or_else(|| TunnelError::ConfigError("Tunnel not configured".to_string()))?;
+        let previous_key = std::mem::replace(&mut config.server_public_key, public_key.to_string());
+        config.server_endpoint = endpoint.to_string();
+        
+        tracing::info!("Switching tunnel endpoint to {}", endpoint);
+        let remove = (previous_key != public_key).then_some(previous_key.as_str());
+        set_peer(&config, remove).await?;
+        *self.config.write() = Some(config);
+        Ok(())
+    }
+    
+    /// Time of the gateway's most recent handshake
+    pub async fn latest_handshake(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let config = self.config()?;
+        let handshake = latest_handshake(&config.server_public_key).await
+            .or_else(|| self.stats.read().last_handshake)?;
+        self.stats.write().last_handshake = Some(handshake);
+        Some(handshake)
+    }
+    
+    /// Wait until a handshake at or after `since` completes
+    pub async fn wait_for_handshake(&self, since: chrono::DateTime<chrono::Utc>, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Handshake times only have second resolution
+            if self.latest_handshake().await.is_some_and(|at| at.timestamp() >= since.timestamp()) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+    
+    /// Suspend the tunnel's routes for `duration` so traffic reaches a
+    /// captive portal directly
+    ///
+    /// The interface stays configured; only routing changes.
+    pub async fn enter_pass_through(&self, duration: Duration) -> Result<PassThroughWindow, TunnelError> {
+        let config = self.config()
+            .ok_or_else(|| TunnelError::ConfigError("Tunnel not configured".to_string()))?;
+        for route in tunnel_routes(&config) {
+            if let Err(e) = crate::split_tunnel::delete_route(&route).await {
+                tracing::warn!("Failed to suspend route {}: {}", route.destination, e);
+            }
+        }
+        
+        let window = PassThroughWindow::new(duration);
+        *self.pass_through.write() = Some(window);
+        *self.state.write() = TunnelState::PassThrough;
+        tracing::warn!("Tunnel pass-through enabled for {}s", duration.as_secs());
+        Ok(window)
+    }
+    
+    /// Restore the tunnel's routes and handshake again
+    pub async fn exit_pass_through(&self) -> Result<(), TunnelError> {
+        if self.pass_through.write().take().is_none() {
+            return Ok(());
+        }
+        let config = self.config()
+            .ok_or_else(|| TunnelError::ConfigError("Tunnel not configured".to_string()))?;
+        for route in tunnel_routes(&config) {
+            crate::split_tunnel::add_route(&route, None).await
+                .map_err(|e| TunnelError::ConfigError(e.to_string()))?;
+        }
+        
+        *self.state.write() = TunnelState::Connected;
+        tracing::info!("Tunnel pass-through ended");
+        self.rehandshake().await
+    }
+    
+    /// Active pass-through window, if any
+    pub fn pass_through(&self) -> Option<PassThroughWindow> {
+        *self.pass_through.read()
+    }
+    
     // Windows implementation
     #[cfg(target_os = "windows")]
     async fn connect_windows(&self, config: &TunnelConfig) -> Result<(), TunnelError> {
@@ -174,6 +284,78 @@ impl TunnelManager {
     }
 }
 
+fn tunnel_routes(config: &TunnelConfig) -> Vec<PlannedRoute> {
+    config.allowed_ips.iter()
+        .filter_map(|ip| ip.parse::<Cidr>().ok())
+        .map(|destination| PlannedRoute { destination, target: RouteTarget::Tunnel })
+        .collect()
+}
+
+/// Reprogram the gateway peer, optionally replacing another one
+///
+/// Setting the endpoint makes WireGuard handshake from the current source
+/// address, and setting the keepalive sends a packet straight away so the
+/// handshake doesn't wait for user traffic.
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+async fn set_peer(config: &TunnelConfig, remove: Option<&str>) -> Result<(), TunnelError> {
+    let mut args = vec!["set".to_string(), TUNNEL_INTERFACE.to_string()];
+    if let Some(previous) = remove {
+        args.extend(["peer".to_string(), previous.to_string(), "remove".to_string()]);
+    }
+    args.extend([
+        "peer".to_string(),
+        config.server_public_key.clone(),
+        "endpoint".to_string(),
+        config.server_endpoint.clone(),
+        "allowed-ips".to_string(),
+        config.allowed_ips.join(","),
+        "persistent-keepalive".to_string(),
+        config.keepalive.to_string(),
+    ]);
+    
+    let output = tokio::process::Command::new("wg")
+        .args(&args)
+        .output()
+        .await
+        .map_err(|e| TunnelError::HandshakeError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(TunnelError::HandshakeError(
+            String::from_utf8_lossy(&output.stderr).to_string()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+async fn latest_handshake(public_key: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::TimeZone;
+    
+    // <public key>\t<unix seconds, 0 if never>
+    let output = tokio::process::Command::new("wg")
+        .args(["show", TUNNEL_INTERFACE, "latest-handshakes"])
+        .output()
+        .await
+        .ok()?;
+    let seconds = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.split_once('\t'))
+        .find(|(key, _)| *key == public_key)
+        .and_then(|(_, at)| at.trim().parse::<i64>().ok())
+        .filter(|at| *at > 0)?;
+    chrono::Utc.timestamp_opt(seconds, 0).single()
+}
+
+// Mobile VPN services re-handshake on network changes themselves
+#[cfg(any(target_os = "ios", target_os = "android"))]
+async fn set_peer(_config: &TunnelConfig, _remove: Option<&str>) -> Result<(), TunnelError> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "ios", target_os = "android"))]
+async fn latest_handshake(_public_key: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    None
+}
+
 impl Default for TunnelManager {
     fn default() -> Self { Self::new() }
 }
@@ -192,3 +374,46 @@ pub enum TunnelError {
     #[error("Connection lost: {0}")]
     ConnectionLost(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TunnelConfig {
+        TunnelConfig {
+            server_endpoint: "198.51.100.10:51820".to_string(),
+            server_public_key: "gateway-key".to_string(),
+            client_private_key: "client-key".to_string(),
+            client_ip: "100.64.0.2/32".to_string(),
+            dns_servers: vec!["100.64.0.1".to_string()],
+            allowed_ips: vec!["10.1.2.3/8".to_string(), "fd00::/8".to_string(), "not-a-network".to_string()],
+            mtu: 1420,
+            keepalive: 25,
+            policies: Vec::new(),
+            split_tunnel: None,
+        }
+    }
+
+    #[test]
+    fn test_tunnel_routes_from_allowed_ips() {
+        let routes = tunnel_routes(&config());
+        assert_eq!(routes, vec![
+            PlannedRoute { destination: "10.0.0.0/8".parse().unwrap(), target: RouteTarget::Tunnel },
+            PlannedRoute { destination: "fd00::/8".parse().unwrap(), target: RouteTarget::Tunnel },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_roaming_requires_a_configured_tunnel() {
+        let tunnel = TunnelManager::new();
+        assert!(matches!(tunnel.rehandshake().await, Err(TunnelError::ConfigError(_))));
+        assert!(matches!(tunnel.switch_endpoint("203.0.113.5:51820", "other-key").await, Err(TunnelError::ConfigError(_))));
+        assert!(matches!(tunnel.enter_pass_through(Duration::from_secs(60)).await, Err(TunnelError::ConfigError(_))));
+        assert!(tunnel.latest_handshake().await.is_none());
+
+        // Leaving a pass-through that never started is a no-op
+        assert!(tunnel.exit_pass_through().await.is_ok());
+        assert!(tunnel.pass_through().is_none());
+        assert_eq!(tunnel.state(), TunnelState::Disconnected);
+    }
+}
@@ -1,13 +1,59 @@
 //! Azure ExpressRoute integration
 //!
 //! Provides connectivity via Azure ExpressRoute and Virtual WAN.
+//!
+//! Circuits and peerings are managed through the Azure Resource Manager
+//! REST API. A circuit's lifecycle is: create it, hand its service key to
+//! the connectivity provider, wait for the provider to provision the
+//! cross-connect, configure private peering with our BGP parameters, then
+//! poll peering stats and circuit metrics into `ConnectionHealth`.
 
-use crate::ConnectorError;
+use crate::{BgpConfig, BgpState, CloudConnectorService, ConnectionHealth, ConnectionStatus, ConnectionType, ConnectorError};
+use chrono::{DateTime, Utc};
+use ipnet::{IpNet, Ipv4Net};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+const MANAGEMENT_ENDPOINT: &str = "https://management.azure.com";
+const LOGIN_ENDPOINT: &str = "https://login.microsoftonline.com";
+const NETWORK_API_VERSION: &str = "2023-09-01";
+const METRICS_API_VERSION: &str = "2018-01-01";
+
+/// Microsoft's ASN on ExpressRoute peerings
+pub const AZURE_ASN: u32 = 12076;
+
+/// ASNs Azure reserves internally and rejects as a peer ASN
+const RESERVED_ASNS: std::ops::RangeInclusive<u32> = 65515..=65520;
+
+/// Service principal used for Resource Manager calls
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AzureCredentials {
+    pub tenant_id: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub subscription_id: String,
+}
 
 /// Azure ExpressRoute manager
 pub struct AzureConnectorManager {
-    // Azure SDK client would go here
+    http: reqwest::Client,
+    credentials: Option<AzureCredentials>,
+    management_endpoint: String,
+    login_endpoint: String,
+    /// Cached bearer token and its expiry
+    token: parking_lot::Mutex<Option<(String, DateTime<Utc>)>>,
+    /// Interval between provisioning and async-operation polls
+    poll_interval: Duration,
+    /// How long to wait for the provider to provision a circuit
+    provisioning_timeout: Duration,
+    /// Circuits by cloud connection id
+    circuits: dashmap::DashMap<Uuid, ExpressRouteCircuit>,
+    /// Service key handoffs by circuit resource id
+    handoffs: dashmap::DashMap<String, ServiceKeyHandoff>,
 }
 
 /// Azure ExpressRoute peering location
@@ -22,16 +68,88 @@ pub struct ExpressRoutePeeringLocation {
 /// Azure ExpressRoute circuit details
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExpressRouteCircuit {
+    /// Resource Manager id
     pub id: String,
     pub name: String,
+    pub resource_group: String,
+    pub location: String,
     pub service_key: String,
     pub service_provider: String,
     pub peering_location: String,
     pub bandwidth_mbps: u32,
+    /// Resource state: Succeeded, Updating, Deleting, Failed
+    pub provisioning_state: String,
     pub circuit_provisioning_state: String,
+    /// NotProvisioned, Provisioning, Provisioned, Deprovisioning
     pub service_provider_provisioning_state: String,
 }
 
+impl ExpressRouteCircuit {
+    /// Provider has completed the cross-connect
+    pub fn is_provider_provisioned(&self) -> bool {
+        self.service_provider_provisioning_state == "Provisioned"
+    }
+    
+    /// Lifecycle status before BGP health is known
+    pub fn connection_status(&self) -> ConnectionStatus {
+        match (
+            self.provisioning_state.as_str(),
+            self.circuit_provisioning_state.as_str(),
+            self.service_provider_provisioning_state.as_str(),
+        ) {
+            ("Failed", _, _) => ConnectionStatus::Down,
+            ("Deleting", _, _) | (_, _, "Deprovisioning") => ConnectionStatus::Deleting,
+            (_, state, _) if state != "Enabled" => ConnectionStatus::Down,
+            (_, _, "NotProvisioned") => ConnectionStatus::Pending,
+            (_, _, "Provisioned") => ConnectionStatus::Active,
+            _ => ConnectionStatus::Provisioning,
+        }
+    }
+}
+
+/// Circuit to create
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExpressRouteCircuitSpec {
+    pub name: String,
+    pub resource_group: String,
+    /// Azure region holding the circuit resource
+    pub location: String,
+    pub service_provider: String,
+    pub peering_location: String,
+    pub bandwidth_mbps: u32,
+    pub sku_tier: ExpressRouteSkuTier,
+    pub sku_family: ExpressRouteSkuFamily,
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpressRouteSkuTier {
+    Local,
+    Standard,
+    Premium,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpressRouteSkuFamily {
+    MeteredData,
+    UnlimitedData,
+}
+
+/// Service key shared with the connectivity provider
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServiceKeyHandoff {
+    pub circuit_id: String,
+    pub service_key: String,
+    pub service_provider: String,
+    pub peering_location: String,
+    pub issued_at: DateTime<Utc>,
+    /// When the key was passed to the provider
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Provider's order or ticket number
+    pub provider_reference: Option<String>,
+    pub provisioned_at: Option<DateTime<Utc>>,
+}
+
 /// Azure ExpressRoute peering configuration
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExpressRoutePeering {
@@ -50,9 +168,119 @@ pub enum PeeringType {
     MicrosoftPeering,
 }
 
+impl PeeringType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AzurePrivatePeering => "AzurePrivatePeering",
+            Self::AzurePublicPeering => "AzurePublicPeering",
+            Self::MicrosoftPeering => "MicrosoftPeering",
+        }
+    }
+}
+
+impl ExpressRoutePeering {
+    /// Private peering from our BGP parameters. Each /30 carries our router
+    /// on the first usable address and Microsoft's on the second; the
+    /// primary link must match `bgp.our_ip` / `bgp.cloud_ip`.
+    pub fn private(
+        bgp: &BgpConfig,
+        vlan_id: u16,
+        primary_peer_subnet: &str,
+        secondary_peer_subnet: &str,
+    ) -> Result<Self, ConnectorError> {
+        if bgp.our_asn == AZURE_ASN || RESERVED_ASNS.contains(&bgp.our_asn) {
+            return Err(ConnectorError::BgpError(format!("ASN {} is reserved by Azure", bgp.our_asn)));
+        }
+        if !(1..=4094).contains(&vlan_id) {
+            return Err(ConnectorError::ValidationError(format!("VLAN {} out of range", vlan_id)));
+        }
+        
+        let primary = peer_subnet(primary_peer_subnet)?;
+        let secondary = peer_subnet(secondary_peer_subnet)?;
+        if primary == secondary {
+            return Err(ConnectorError::ValidationError("Primary and secondary peer subnets must differ".to_string()));
+        }
+        
+        let (ours, azure) = peer_addresses(&primary);
+        if bgp.our_ip != IpAddr::V4(ours) || bgp.cloud_ip != IpAddr::V4(azure) {
+            return Err(ConnectorError::BgpError(format!(
+                "BGP peers {} / {} do not match primary subnet {} (expected {} / {})",
+                bgp.our_ip, bgp.cloud_ip, primary, ours, azure
+            )));
+        }
+        
+        Ok(Self {
+            peering_type: PeeringType::AzurePrivatePeering,
+            primary_peer_subnet: primary.to_string(),
+            secondary_peer_subnet: secondary.to_string(),
+            vlan_id,
+            peer_asn: bgp.our_asn,
+            shared_key: bgp.md5_auth.clone(),
+        })
+    }
+}
+
+/// Peering state reported by Azure
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExpressRoutePeeringStatus {
+    pub peering_type: String,
+    /// Enabled or Disabled
+    pub state: String,
+    pub provisioning_state: String,
+    pub azure_asn: u32,
+    pub primary_azure_port: Option<String>,
+    pub secondary_azure_port: Option<String>,
+}
+
+/// Settings for provisioning an ExpressRoute cloud connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExpressRouteProvisioning {
+    pub resource_group: String,
+    pub location: String,
+    pub service_provider: String,
+    pub sku_tier: ExpressRouteSkuTier,
+    pub sku_family: ExpressRouteSkuFamily,
+    /// /30 for the primary link
+    pub primary_peer_subnet: String,
+    /// /30 for the secondary link
+    pub secondary_peer_subnet: String,
+}
+
 impl AzureConnectorManager {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            http: reqwest::Client::new(),
+            credentials: None,
+            management_endpoint: MANAGEMENT_ENDPOINT.to_string(),
+            login_endpoint: LOGIN_ENDPOINT.to_string(),
+            token: parking_lot::Mutex::new(None),
+            poll_interval: Duration::from_secs(30),
+            provisioning_timeout: Duration::from_secs(14 * 24 * 3600),
+            circuits: dashmap::DashMap::new(),
+            handoffs: dashmap::DashMap::new(),
+        }
+    }
+    
+    pub fn with_credentials(mut self, credentials: AzureCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+    
+    /// Override the Resource Manager and login endpoints (sovereign clouds)
+    pub fn with_endpoints(mut self, management: &str, login: &str) -> Self {
+        self.management_endpoint = management.trim_end_matches('/').to_string();
+        self.login_endpoint = login.trim_end_matches('/').to_string();
+        self
+    }
+    
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    
+    pub fn with_provisioning_timeout(mut self, timeout: Duration) -> Self {
+        self.provisioning_timeout = timeout;
+        self
     }
     
     /// List available peering locations
@@ -79,36 +307,351 @@ impl AzureConnectorManager {
         ])
     }
     
-    /// Create ExpressRoute circuit
-    pub async fn create_circuit(
+    /// Create ExpressRoute circuit and record its service key for handoff
+    pub async fn create_circuit(&self, spec: &ExpressRouteCircuitSpec) -> Result<ExpressRouteCircuit, ConnectorError> {
+        let id = format!(
+            "/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Network/expressRouteCircuits/{}",
+            self.credentials()?.subscription_id, spec.resource_group, spec.name
+        );
+        let (tier, family) = (format!("{:?}", spec.sku_tier), format!("{:?}", spec.sku_family));
+        let body = json!({
+            "location": spec.location,
+            "sku": {
+                "name": format!("{}_{}", tier, family),
+                "tier": tier,
+                "family": family,
+            },
+            "tags": spec.tags,
+            "properties": {
+                "serviceProviderProperties": {
+                    "serviceProviderName": spec.service_provider,
+                    "peeringLocation": spec.peering_location,
+                    "bandwidthInMbps": spec.bandwidth_mbps,
+                },
+            },
+        });
+        
+        let response = self.send(reqwest::Method::PUT, &self.resource_url(&id, NETWORK_API_VERSION), Some(body)).await?;
+        self.wait_for_operation(response).await?;
+        
+        let circuit = self.get_circuit(&spec.resource_group, &spec.name).await?;
+        self.handoffs.insert(circuit.id.clone(), ServiceKeyHandoff {
+            circuit_id: circuit.id.clone(),
+            service_key: circuit.service_key.clone(),
+            service_provider: circuit.service_provider.clone(),
+            peering_location: circuit.peering_location.clone(),
+            issued_at: Utc::now(),
+            delivered_at: None,
+            provider_reference: None,
+            provisioned_at: None,
+        });
+        
+        tracing::info!(
+            "Created ExpressRoute circuit {} at {}; service key pending handoff to {}",
+            circuit.name, circuit.peering_location, circuit.service_provider
+        );
+        
+        Ok(circuit)
+    }
+    
+    /// Fetch a circuit's current state
+    pub async fn get_circuit(&self, resource_group: &str, name: &str) -> Result<ExpressRouteCircuit, ConnectorError> {
+        let id = format!(
+            "/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Network/expressRouteCircuits/{}",
+            self.credentials()?.subscription_id, resource_group, name
+        );
+        let response = self.send(reqwest::Method::GET, &self.resource_url(&id, NETWORK_API_VERSION), None).await?;
+        parse_circuit(resource_group, &response.body)
+    }
+    
+    /// Delete a circuit. Azure refuses while the provider still has it
+    /// provisioned, so the provider must deprovision first.
+    pub async fn delete_circuit(&self, circuit: &ExpressRouteCircuit) -> Result<(), ConnectorError> {
+        let current = self.get_circuit(&circuit.resource_group, &circuit.name).await?;
+        if current.is_provider_provisioned() {
+            return Err(ConnectorError::ProvisioningError(format!(
+                "Circuit {} is still provisioned by {}; ask the provider to deprovision service key {}",
+                current.name, current.service_provider, current.service_key
+            )));
+        }
+        
+        let response = self.send(reqwest::Method::DELETE, &self.resource_url(&circuit.id, NETWORK_API_VERSION), None).await?;
+        self.wait_for_operation(response).await?;
+        self.handoffs.remove(&circuit.id);
+        self.circuits.retain(|_, c| c.id != circuit.id);
+        Ok(())
+    }
+    
+    /// Record that the service key was passed to the provider
+    pub fn record_service_key_delivery(
+        &self,
+        circuit_id: &str,
+        provider_reference: Option<String>,
+    ) -> Result<ServiceKeyHandoff, ConnectorError> {
+        let mut handoff = self.handoffs.get_mut(circuit_id)
+            .ok_or_else(|| ConnectorError::ProviderError(format!("No service key issued for {}", circuit_id)))?;
+        handoff.delivered_at = Some(Utc::now());
+        handoff.provider_reference = provider_reference;
+        Ok(handoff.clone())
+    }
+    
+    pub fn service_key_handoff(&self, circuit_id: &str) -> Option<ServiceKeyHandoff> {
+        self.handoffs.get(circuit_id).map(|h| h.clone())
+    }
+    
+    /// Handoffs the provider has not provisioned yet
+    pub fn pending_handoffs(&self) -> Vec<ServiceKeyHandoff> {
+        self.handoffs.iter()
+            .filter(|h| h.provisioned_at.is_none())
+            .map(|h| h.clone())
+            .collect()
+    }
+    
+    /// Poll until the provider reports the circuit provisioned
+    pub async fn wait_for_provider_provisioning(
         &self,
-        name: &str,
         resource_group: &str,
-        peering_location: &str,
-        service_provider: &str,
-        bandwidth_mbps: u32,
+        name: &str,
     ) -> Result<ExpressRouteCircuit, ConnectorError> {
-        Ok(ExpressRouteCircuit {
-            id: format!("/subscriptions/xxx/resourceGroups/{}/providers/Microsoft.Network/expressRouteCircuits/{}", 
-                resource_group, name),
-            name: name.to_string(),
-            service_key: uuid::Uuid::new_v4().to_string(),
-            service_provider: service_provider.to_string(),
-            peering_location: peering_location.to_string(),
-            bandwidth_mbps,
-            circuit_provisioning_state: "Enabled".to_string(),
-            service_provider_provisioning_state: "NotProvisioned".to_string(),
-        })
+        let deadline = tokio::time::Instant::now() + self.provisioning_timeout;
+        
+        loop {
+            let circuit = self.get_circuit(resource_group, name).await?;
+            match circuit.service_provider_provisioning_state.as_str() {
+                "Provisioned" => {
+                    if let Some(mut handoff) = self.handoffs.get_mut(&circuit.id) {
+                        handoff.provisioned_at.get_or_insert_with(Utc::now);
+                    }
+                    tracing::info!("ExpressRoute circuit {} provisioned by {}", name, circuit.service_provider);
+                    return Ok(circuit);
+                }
+                "Deprovisioning" => {
+                    return Err(ConnectorError::ProvisioningError(format!(
+                        "Provider is deprovisioning circuit {}", name
+                    )));
+                }
+                state => {
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(ConnectorError::ProvisioningError(format!(
+                            "Circuit {} still {} after {}s", name, state, self.provisioning_timeout.as_secs()
+                        )));
+                    }
+                    tracing::debug!("ExpressRoute circuit {} provider state {}", name, state);
+                }
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
     }
     
     /// Configure private peering
     pub async fn configure_private_peering(
         &self,
-        circuit_name: &str,
+        circuit: &ExpressRouteCircuit,
         peering: ExpressRoutePeering,
-    ) -> Result<(), ConnectorError> {
-        // In production: call Azure API
-        Ok(())
+    ) -> Result<ExpressRoutePeeringStatus, ConnectorError> {
+        if !circuit.is_provider_provisioned() {
+            return Err(ConnectorError::ProvisioningError(format!(
+                "Circuit {} is {} by the provider", circuit.name, circuit.service_provider_provisioning_state
+            )));
+        }
+        
+        let mut properties = json!({
+            "peeringType": peering.peering_type.as_str(),
+            "peerASN": peering.peer_asn,
+            "primaryPeerAddressPrefix": peering.primary_peer_subnet,
+            "secondaryPeerAddressPrefix": peering.secondary_peer_subnet,
+            "vlanId": peering.vlan_id,
+            "state": "Enabled",
+        });
+        if let Some(key) = &peering.shared_key {
+            properties["sharedKey"] = json!(key);
+        }
+        
+        let url = self.resource_url(&peering_id(circuit, &peering.peering_type), NETWORK_API_VERSION);
+        let response = self.send(reqwest::Method::PUT, &url, Some(json!({ "properties": properties }))).await?;
+        self.wait_for_operation(response).await?;
+        
+        let response = self.send(reqwest::Method::GET, &url, None).await?;
+        let props = &response.body["properties"];
+        Ok(ExpressRoutePeeringStatus {
+            peering_type: str_field(props, "peeringType"),
+            state: str_field(props, "state"),
+            provisioning_state: str_field(props, "provisioningState"),
+            azure_asn: props["azureASN"].as_u64().unwrap_or(AZURE_ASN as u64) as u32,
+            primary_azure_port: props["primaryAzurePort"].as_str().map(str::to_string),
+            secondary_azure_port: props["secondaryAzurePort"].as_str().map(str::to_string),
+        })
+    }
+    
+    /// Current health of a circuit's private peering. Byte counters come
+    /// from peering stats, BGP state and prefix counts from the primary
+    /// link's route table summary, and availability from Azure Monitor.
+    pub async fn circuit_health(
+        &self,
+        circuit: &ExpressRouteCircuit,
+        bgp: &BgpConfig,
+    ) -> Result<ConnectionHealth, ConnectorError> {
+        let peering = peering_id(circuit, &PeeringType::AzurePrivatePeering);
+        
+        let stats = self.send(
+            reqwest::Method::GET,
+            &self.resource_url(&format!("{}/stats", peering), NETWORK_API_VERSION),
+            None,
+        ).await?.body;
+        let counter = |field: &str| stats[field].as_u64().unwrap_or(0);
+        
+        let metrics = self.circuit_metrics(circuit).await?;
+        let bgp_availability = metrics.get("BgpAvailability").copied();
+        let arp_availability = metrics.get("ArpAvailability").copied();
+        
+        // Route summary is best effort; fall back to BGP availability
+        let summary = match self.route_table_summary(&peering, bgp).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                tracing::debug!("Route table summary for {} unavailable: {}", circuit.name, e);
+                None
+            }
+        };
+        let (bgp_state, uptime, prefixes_advertised) = match summary {
+            Some(summary) => summary,
+            None => (
+                match bgp_availability {
+                    Some(availability) if availability > 0.0 => BgpState::Established,
+                    _ => BgpState::Idle,
+                },
+                0,
+                bgp.advertised_prefixes.len() as u32,
+            ),
+        };
+        
+        Ok(ConnectionHealth {
+            bgp_state,
+            bgp_uptime_secs: uptime,
+            prefixes_received: bgp.received_prefixes.len() as u32,
+            prefixes_advertised,
+            rx_bytes: counter("primarybytesIn") + counter("secondarybytesIn"),
+            tx_bytes: counter("primarybytesOut") + counter("secondarybytesOut"),
+            rx_packets: 0,
+            tx_packets: 0,
+            errors: 0,
+            latency_ms: 0.0,
+            jitter_ms: 0.0,
+            // Missed ARP probes to the MSEEs are the closest loss signal
+            packet_loss: arp_availability.map(|a| ((100.0 - a) / 100.0).clamp(0.0, 1.0)).unwrap_or(0.0),
+            last_checked: Utc::now(),
+        })
+    }
+    
+    /// Create the circuit for an ExpressRoute cloud connection. Returns the
+    /// service key handoff to send to the provider.
+    pub async fn provision_circuit(
+        &self,
+        service: &CloudConnectorService,
+        connection_id: &Uuid,
+        options: &ExpressRouteProvisioning,
+    ) -> Result<ServiceKeyHandoff, ConnectorError> {
+        let connection = service.get_connection(connection_id)
+            .ok_or(ConnectorError::ConnectionNotFound(*connection_id))?;
+        let ConnectionType::ExpressRoute { peering_location, bandwidth_mbps } = &connection.connection_type else {
+            return Err(ConnectorError::ValidationError(format!("{} is not an ExpressRoute connection", connection.name)));
+        };
+        
+        // Fail before creating billable resources
+        ExpressRoutePeering::private(
+            &connection.bgp_config,
+            connection.vlan_id,
+            &options.primary_peer_subnet,
+            &options.secondary_peer_subnet,
+        )?;
+        
+        let spec = ExpressRouteCircuitSpec {
+            name: format!("opensase-{}", connection.id),
+            resource_group: options.resource_group.clone(),
+            location: options.location.clone(),
+            service_provider: options.service_provider.clone(),
+            peering_location: peering_location.clone(),
+            bandwidth_mbps: *bandwidth_mbps,
+            sku_tier: options.sku_tier,
+            sku_family: options.sku_family,
+            tags: HashMap::from([
+                ("opensase-connection".to_string(), connection.id.to_string()),
+                ("opensase-tenant".to_string(), connection.tenant_id.to_string()),
+            ]),
+        };
+        
+        let circuit = self.create_circuit(&spec).await?;
+        service.update_status(connection_id, circuit.connection_status())?;
+        let handoff = self.service_key_handoff(&circuit.id)
+            .ok_or_else(|| ConnectorError::ProviderError("Service key not recorded".to_string()))?;
+        self.circuits.insert(*connection_id, circuit);
+        
+        Ok(handoff)
+    }
+    
+    /// Wait for the provider, configure private peering and take the first
+    /// health reading
+    pub async fn activate_circuit(
+        &self,
+        service: &CloudConnectorService,
+        connection_id: &Uuid,
+        options: &ExpressRouteProvisioning,
+    ) -> Result<ExpressRoutePeeringStatus, ConnectorError> {
+        let connection = service.get_connection(connection_id)
+            .ok_or(ConnectorError::ConnectionNotFound(*connection_id))?;
+        let circuit = self.circuit_for(connection_id)?;
+        
+        service.update_status(connection_id, ConnectionStatus::Provisioning)?;
+        let circuit = match self.wait_for_provider_provisioning(&circuit.resource_group, &circuit.name).await {
+            Ok(circuit) => circuit,
+            Err(e) => {
+                service.update_status(connection_id, ConnectionStatus::Down)?;
+                return Err(e);
+            }
+        };
+        self.circuits.insert(*connection_id, circuit.clone());
+        
+        let peering = ExpressRoutePeering::private(
+            &connection.bgp_config,
+            connection.vlan_id,
+            &options.primary_peer_subnet,
+            &options.secondary_peer_subnet,
+        )?;
+        let status = self.configure_private_peering(&circuit, peering).await?;
+        
+        self.refresh_health(service, connection_id).await?;
+        Ok(status)
+    }
+    
+    /// Poll Azure and feed the connection's health
+    pub async fn refresh_health(
+        &self,
+        service: &CloudConnectorService,
+        connection_id: &Uuid,
+    ) -> Result<ConnectionHealth, ConnectorError> {
+        let connection = service.get_connection(connection_id)
+            .ok_or(ConnectorError::ConnectionNotFound(*connection_id))?;
+        let tracked = self.circuit_for(connection_id)?;
+        
+        let circuit = self.get_circuit(&tracked.resource_group, &tracked.name).await?;
+        self.circuits.insert(*connection_id, circuit.clone());
+        
+        // Lifecycle problems override whatever BGP reports
+        let lifecycle = circuit.connection_status();
+        if lifecycle != ConnectionStatus::Active {
+            service.update_status(connection_id, lifecycle)?;
+            return Ok(connection.health);
+        }
+        
+        let health = self.circuit_health(&circuit, &connection.bgp_config).await?;
+        service.update_health(connection_id, health.clone())?;
+        Ok(health)
+    }
+    
+    /// Circuit tracked for a cloud connection
+    pub fn circuit_for(&self, connection_id: &Uuid) -> Result<ExpressRouteCircuit, ConnectorError> {
+        self.circuits.get(connection_id)
+            .map(|c| c.clone())
+            .ok_or_else(|| ConnectorError::ProviderError(format!("No ExpressRoute circuit for connection {}", connection_id)))
     }
     
     /// Create ExpressRoute Gateway
@@ -137,6 +680,186 @@ impl AzureConnectorManager {
         Ok(connection_id)
     }
     
+    // -------------------------------------------------------------------------
+    // Resource Manager plumbing
+    // -------------------------------------------------------------------------
+    
+    fn credentials(&self) -> Result<&AzureCredentials, ConnectorError> {
+        self.credentials.as_ref()
+            .ok_or_else(|| ConnectorError::ProviderError("Azure credentials not configured".to_string()))
+    }
+    
+    fn resource_url(&self, id: &str, api_version: &str) -> String {
+        format!("{}{}?api-version={}", self.management_endpoint, id, api_version)
+    }
+    
+    /// Client-credentials token for Resource Manager, cached until shortly
+    /// before expiry
+    async fn access_token(&self) -> Result<String, ConnectorError> {
+        if let Some((token, expires_at)) = self.token.lock().clone() {
+            if expires_at > Utc::now() + chrono::Duration::seconds(60) {
+                return Ok(token);
+            }
+        }
+        
+        let credentials = self.credentials()?;
+        let response = self.http
+            .post(format!("{}/{}/oauth2/v2.0/token", self.login_endpoint, credentials.tenant_id))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", credentials.client_id.as_str()),
+                ("client_secret", credentials.client_secret.as_str()),
+                ("scope", "https://management.azure.com/.default"),
+            ])
+            .send()
+            .await
+            .map_err(|e| ConnectorError::ProviderError(format!("Azure login failed: {}", e)))?;
+        
+        let status = response.status();
+        let body: Value = response.json().await
+            .map_err(|e| ConnectorError::ProviderError(format!("Azure login failed: {}", e)))?;
+        let Some(token) = body["access_token"].as_str().filter(|_| status.is_success()) else {
+            return Err(ConnectorError::ProviderError(format!(
+                "Azure login failed: {}", body["error_description"].as_str().unwrap_or("no token returned")
+            )));
+        };
+        
+        let expires_in = body["expires_in"].as_i64().unwrap_or(3600);
+        *self.token.lock() = Some((token.to_string(), Utc::now() + chrono::Duration::seconds(expires_in)));
+        Ok(token.to_string())
+    }
+    
+    async fn send(&self, method: reqwest::Method, url: &str, body: Option<Value>) -> Result<ArmResponse, ConnectorError> {
+        let mut request = self.http.request(method.clone(), url)
+            .bearer_auth(self.access_token().await?);
+        if let Some(body) = &body {
+            request = request.json(body);
+        } else if method == reqwest::Method::POST {
+            request = request.header(reqwest::header::CONTENT_LENGTH, 0);
+        }
+        
+        let response = request.send().await
+            .map_err(|e| ConnectorError::ProviderError(format!("{} {}: {}", method, url, e)))?;
+        let status = response.status().as_u16();
+        let header = |name: &str| response.headers().get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let async_operation = header("Azure-AsyncOperation");
+        let location = header("Location");
+        let body = response.bytes().await
+            .map(|b| serde_json::from_slice(&b).unwrap_or(Value::Null))
+            .unwrap_or(Value::Null);
+        
+        if status >= 400 {
+            let error = &body["error"];
+            return Err(ConnectorError::ProviderError(format!(
+                "{} {} returned {}: {} {}",
+                method, url, status,
+                error["code"].as_str().unwrap_or(""),
+                error["message"].as_str().unwrap_or(""),
+            )));
+        }
+        
+        Ok(ArmResponse { status, async_operation, location, body })
+    }
+    
+    /// Follow a long-running operation to completion and return its result
+    async fn wait_for_operation(&self, response: ArmResponse) -> Result<Value, ConnectorError> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(3600);
+        let poll = self.poll_interval.min(Duration::from_secs(10));
+        
+        if let Some(url) = &response.async_operation {
+            loop {
+                let status = self.send(reqwest::Method::GET, url, None).await?;
+                match status.body["status"].as_str().unwrap_or("InProgress") {
+                    "Succeeded" => return Ok(response.body),
+                    "Failed" | "Canceled" => {
+                        return Err(ConnectorError::ProvisioningError(format!(
+                            "Azure operation {}: {}",
+                            status.body["status"].as_str().unwrap_or_default(),
+                            status.body["error"]["message"].as_str().unwrap_or("no details")
+                        )));
+                    }
+                    _ if tokio::time::Instant::now() >= deadline => {
+                        return Err(ConnectorError::ProvisioningError("Azure operation timed out".to_string()));
+                    }
+                    _ => tokio::time::sleep(poll).await,
+                }
+            }
+        }
+        
+        if response.status == 202 {
+            if let Some(url) = &response.location {
+                loop {
+                    let result = self.send(reqwest::Method::GET, url, None).await?;
+                    if result.status != 202 {
+                        return Ok(result.body);
+                    }
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(ConnectorError::ProvisioningError("Azure operation timed out".to_string()));
+                    }
+                    tokio::time::sleep(poll).await;
+                }
+            }
+        }
+        
+        Ok(response.body)
+    }
+    
+    /// Average of each availability metric over the last five minutes
+    async fn circuit_metrics(&self, circuit: &ExpressRouteCircuit) -> Result<HashMap<String, f64>, ConnectorError> {
+        let url = format!(
+            "{}&metricnames=BgpAvailability,ArpAvailability&timespan=PT5M&interval=PT1M&aggregation=Average",
+            self.resource_url(&format!("{}/providers/microsoft.insights/metrics", circuit.id), METRICS_API_VERSION)
+        );
+        let response = self.send(reqwest::Method::GET, &url, None).await?;
+        
+        let mut metrics = HashMap::new();
+        for metric in response.body["value"].as_array().into_iter().flatten() {
+            let name = metric["name"]["value"].as_str().unwrap_or_default();
+            let samples: Vec<f64> = metric["timeseries"].as_array().into_iter().flatten()
+                .flat_map(|series| series["data"].as_array().into_iter().flatten())
+                .filter_map(|point| point["average"].as_f64())
+                .collect();
+            if !samples.is_empty() {
+                metrics.insert(name.to_string(), samples.iter().sum::<f64>() / samples.len() as f64);
+            }
+        }
+        Ok(metrics)
+    }
+    
+    /// BGP state, uptime and prefixes Azure receives from us on the primary
+    /// link, or `None` when our neighbor is absent from the summary
+    async fn route_table_summary(
+        &self,
+        peering: &str,
+        bgp: &BgpConfig,
+    ) -> Result<Option<(BgpState, u64, u32)>, ConnectorError> {
+        let url = self.resource_url(&format!("{}/routeTablesSummary/primary", peering), NETWORK_API_VERSION);
+        let response = self.send(reqwest::Method::POST, &url, None).await?;
+        let result = self.wait_for_operation(response).await?;
+        
+        let neighbor = bgp.our_ip.to_string();
+        let Some(entry) = result["value"].as_array().into_iter().flatten()
+            .find(|e| e["neighbor"].as_str() == Some(neighbor.as_str()))
+        else {
+            return Ok(None);
+        };
+        
+        // Established sessions report a prefix count; others report the state
+        let (state, prefixes) = match &entry["statePfxRcd"] {
+            Value::Number(n) => (BgpState::Established, n.as_u64().unwrap_or(0) as u32),
+            Value::String(s) => match s.parse::<u32>() {
+                Ok(n) => (BgpState::Established, n),
+                Err(_) => (parse_bgp_state(s), 0),
+            },
+            _ => (BgpState::Idle, 0),
+        };
+        let uptime = entry["upDown"].as_str().map(parse_up_down).unwrap_or(0);
+        
+        Ok(Some((state, if state == BgpState::Established { uptime } else { 0 }, prefixes)))
+    }
+    
     /// Generate Terraform configuration
     pub fn generate_terraform(&self, config: &AzureTerraformConfig) -> String {
         format!(r#"
@@ -277,3 +1000,380 @@ pub struct AzureTerraformConfig {
     pub shared_key: Option<String>,
     pub gateway_subnet_id: String,
 }
+
+struct ArmResponse {
+    status: u16,
+    async_operation: Option<String>,
+    location: Option<String>,
+    body: Value,
+}
+
+fn peering_id(circuit: &ExpressRouteCircuit, peering_type: &PeeringType) -> String {
+    format!("{}/peerings/{}", circuit.id, peering_type.as_str())
+}
+
+fn str_field(value: &Value, field: &str) -> String {
+    value[field].as_str().unwrap_or_default().to_string()
+}
+
+fn parse_circuit(resource_group: &str, body: &Value) -> Result<ExpressRouteCircuit, ConnectorError> {
+    let props = &body["properties"];
+    let provider = &props["serviceProviderProperties"];
+    let id = body["id"].as_str()
+        .ok_or_else(|| ConnectorError::ProviderError("Circuit response missing id".to_string()))?;
+    
+    Ok(ExpressRouteCircuit {
+        id: id.to_string(),
+        name: str_field(body, "name"),
+        resource_group: resource_group.to_string(),
+        location: str_field(body, "location"),
+        service_key: str_field(props, "serviceKey"),
+        service_provider: str_field(provider, "serviceProviderName"),
+        peering_location: str_field(provider, "peeringLocation"),
+        bandwidth_mbps: provider["bandwidthInMbps"].as_u64().unwrap_or(0) as u32,
+        provisioning_state: str_field(props, "provisioningState"),
+        circuit_provisioning_state: str_field(props, "circuitProvisioningState"),
+        service_provider_provisioning_state: str_field(props, "serviceProviderProvisioningState"),
+    })
+}
+
+/// A /30 peer subnet
+fn peer_subnet(subnet: &str) -> Result<Ipv4Net, ConnectorError> {
+    match subnet.parse::<IpNet>() {
+        Ok(IpNet::V4(net)) if net.prefix_len() == 30 => Ok(net.trunc()),
+        _ => Err(ConnectorError::ValidationError(format!("Peer subnet {} must be an IPv4 /30", subnet))),
+    }
+}
+
+/// Our router and Microsoft's router addresses in a /30
+pub fn peer_addresses(subnet: &Ipv4Net) -> (std::net::Ipv4Addr, std::net::Ipv4Addr) {
+    let base = u32::from(subnet.network());
+    ((base + 1).into(), (base + 2).into())
+}
+
+fn parse_bgp_state(state: &str) -> BgpState {
+    match state.to_ascii_lowercase().as_str() {
+        "connect" => BgpState::Connect,
+        "active" => BgpState::Active,
+        "opensent" => BgpState::OpenSent,
+        "openconfirm" => BgpState::OpenConfirm,
+        "established" => BgpState::Established,
+        _ => BgpState::Idle,
+    }
+}
+
+/// Parse the summary's `upDown` column: `hh:mm:ss`, `1d02h`, `3w2d`
+fn parse_up_down(value: &str) -> u64 {
+    if value.contains(':') {
+        return value.split(':')
+            .filter_map(|p| p.parse::<u64>().ok())
+            .fold(0, |acc, p| acc * 60 + p);
+    }
+    
+    let mut total = 0;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: u64 = digits.parse().unwrap_or(0);
+        digits.clear();
+        total += n * match c {
+            'y' => 365 * 86400,
+            'w' => 7 * 86400,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            _ => 1,
+        };
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const CIRCUIT_ID: &str = "/subscriptions/sub-1/resourceGroups/rg-net/providers/Microsoft.Network/expressRouteCircuits/opensase-er";
+
+    /// Resource Manager stand-in answering `(method, path fragment)` routes
+    /// with JSON; records the request lines it served
+    async fn arm(routes: Vec<(&'static str, &'static str, Value)>) -> (String, Arc<parking_lot::Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let log = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let seen = log.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head.lines()
+                            .filter_map(|l| l.split_once(':'))
+                            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                let text = String::from_utf8_lossy(&request).to_string();
+                let line = text.lines().next().unwrap_or_default().to_string();
+                let (method, path) = line.split_once(' ').unwrap_or_default();
+                seen.lock().push(format!("{} {}", method, path.split(' ').next().unwrap_or_default()));
+
+                let (status, body) = match routes.iter().find(|(m, p, _)| *m == method && path.contains(p)) {
+                    Some((_, _, body)) => ("200 OK", body.to_string()),
+                    None => ("404 Not Found", json!({ "error": { "code": "NotFound", "message": path } }).to_string()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (base, log)
+    }
+
+    fn manager(base: &str) -> AzureConnectorManager {
+        AzureConnectorManager::new()
+            .with_credentials(AzureCredentials {
+                tenant_id: "tenant-1".to_string(),
+                client_id: "client".to_string(),
+                client_secret: "secret".to_string(),
+                subscription_id: "sub-1".to_string(),
+            })
+            .with_endpoints(&format!("{}/", base), base)
+            .with_poll_interval(Duration::from_millis(10))
+    }
+
+    fn bgp(our_asn: u32, our_ip: &str, cloud_ip: &str) -> BgpConfig {
+        BgpConfig {
+            our_asn,
+            cloud_asn: AZURE_ASN,
+            our_ip: our_ip.parse().unwrap(),
+            cloud_ip: cloud_ip.parse().unwrap(),
+            md5_auth: Some("s3cret".to_string()),
+            advertised_prefixes: vec!["10.10.0.0/16".parse().unwrap(), "10.20.0.0/16".parse().unwrap()],
+            received_prefixes: vec!["172.16.0.0/12".parse().unwrap()],
+            local_preference: 100,
+            med: 0,
+        }
+    }
+
+    fn er_circuit(provisioning: &str, circuit: &str, provider: &str) -> ExpressRouteCircuit {
+        ExpressRouteCircuit {
+            id: CIRCUIT_ID.to_string(),
+            name: "opensase-er".to_string(),
+            resource_group: "rg-net".to_string(),
+            location: "westeurope".to_string(),
+            service_key: "0f1e2d3c-0000-4000-8000-000000000001".to_string(),
+            service_provider: "Equinix".to_string(),
+            peering_location: "Amsterdam".to_string(),
+            bandwidth_mbps: 1000,
+            provisioning_state: provisioning.to_string(),
+            circuit_provisioning_state: circuit.to_string(),
+            service_provider_provisioning_state: provider.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_circuit_lifecycle_status() {
+        for (provisioning, state, provider, expected) in [
+            ("Succeeded", "Enabled", "NotProvisioned", ConnectionStatus::Pending),
+            ("Succeeded", "Enabled", "Provisioning", ConnectionStatus::Provisioning),
+            ("Succeeded", "Enabled", "Provisioned", ConnectionStatus::Active),
+            ("Updating", "Enabled", "Provisioned", ConnectionStatus::Active),
+            ("Succeeded", "Enabled", "Deprovisioning", ConnectionStatus::Deleting),
+            ("Deleting", "Enabled", "Provisioned", ConnectionStatus::Deleting),
+            ("Failed", "Enabled", "Provisioned", ConnectionStatus::Down),
+            ("Succeeded", "Disabled", "Provisioned", ConnectionStatus::Down),
+        ] {
+            let circuit = er_circuit(provisioning, state, provider);
+            assert_eq!(circuit.connection_status(), expected, "{} / {} / {}", provisioning, state, provider);
+            assert_eq!(circuit.is_provider_provisioned(), provider == "Provisioned");
+        }
+    }
+
+    #[test]
+    fn test_private_peering_validation() {
+        let peering = ExpressRoutePeering::private(&bgp(65010, "10.0.0.1", "10.0.0.2"), 100, "10.0.0.2/30", "10.0.0.4/30").unwrap();
+        assert_eq!(peering.primary_peer_subnet, "10.0.0.0/30");
+        assert_eq!(peering.secondary_peer_subnet, "10.0.0.4/30");
+        assert_eq!((peering.vlan_id, peering.peer_asn), (100, 65010));
+        assert_eq!(peering.shared_key.as_deref(), Some("s3cret"));
+        assert_eq!(peer_addresses(&"192.168.7.8/30".parse().unwrap()), ("192.168.7.9".parse().unwrap(), "192.168.7.10".parse().unwrap()));
+
+        let ok = bgp(65010, "10.0.0.1", "10.0.0.2");
+        for (config, vlan, primary, secondary) in [
+            (bgp(AZURE_ASN, "10.0.0.1", "10.0.0.2"), 100, "10.0.0.0/30", "10.0.0.4/30"),
+            (bgp(65515, "10.0.0.1", "10.0.0.2"), 100, "10.0.0.0/30", "10.0.0.4/30"),
+            (ok.clone(), 0, "10.0.0.0/30", "10.0.0.4/30"),
+            (ok.clone(), 4095, "10.0.0.0/30", "10.0.0.4/30"),
+            (ok.clone(), 100, "10.0.0.0/29", "10.0.0.8/30"),
+            (ok.clone(), 100, "fd00::/126", "10.0.0.4/30"),
+            (ok.clone(), 100, "10.0.0.0/30", "10.0.0.1/30"),
+            // Peers swapped: Microsoft takes the second address
+            (bgp(65010, "10.0.0.2", "10.0.0.1"), 100, "10.0.0.0/30", "10.0.0.4/30"),
+        ] {
+            assert!(ExpressRoutePeering::private(&config, vlan, primary, secondary).is_err(), "{} {} {}", vlan, primary, secondary);
+        }
+    }
+
+    #[test]
+    fn test_parse_circuit_and_route_summary_fields() {
+        let body = json!({
+            "id": CIRCUIT_ID,
+            "name": "opensase-er",
+            "location": "westeurope",
+            "properties": {
+                "serviceKey": "0f1e2d3c-0000-4000-8000-000000000001",
+                "provisioningState": "Succeeded",
+                "circuitProvisioningState": "Enabled",
+                "serviceProviderProvisioningState": "NotProvisioned",
+                "serviceProviderProperties": {
+                    "serviceProviderName": "Equinix",
+                    "peeringLocation": "Amsterdam",
+                    "bandwidthInMbps": 1000
+                }
+            }
+        });
+        let circuit = parse_circuit("rg-net", &body).unwrap();
+        assert_eq!((circuit.name.as_str(), circuit.resource_group.as_str(), circuit.bandwidth_mbps), ("opensase-er", "rg-net", 1000));
+        assert_eq!(circuit.service_provider, "Equinix");
+        assert_eq!(circuit.connection_status(), ConnectionStatus::Pending);
+        assert!(parse_circuit("rg-net", &json!({ "name": "x" })).is_err());
+
+        assert_eq!(parse_up_down("01:02:03"), 3723);
+        assert_eq!(parse_up_down("1d02h"), 26 * 3600);
+        assert_eq!(parse_up_down("3w2d"), 23 * 86400);
+        assert_eq!(parse_up_down("never"), 0);
+        assert_eq!(parse_bgp_state("OpenSent"), BgpState::OpenSent);
+        assert_eq!(parse_bgp_state("Idle (Admin)"), BgpState::Idle);
+    }
+
+    #[test]
+    fn test_service_key_handoff() {
+        let manager = AzureConnectorManager::new();
+        assert!(manager.record_service_key_delivery(CIRCUIT_ID, None).is_err());
+
+        let circuit = er_circuit("Succeeded", "Enabled", "NotProvisioned");
+        manager.handoffs.insert(circuit.id.clone(), ServiceKeyHandoff {
+            circuit_id: circuit.id.clone(),
+            service_key: circuit.service_key.clone(),
+            service_provider: circuit.service_provider.clone(),
+            peering_location: circuit.peering_location.clone(),
+            issued_at: Utc::now(),
+            delivered_at: None,
+            provider_reference: None,
+            provisioned_at: None,
+        });
+
+        let handoff = manager.record_service_key_delivery(CIRCUIT_ID, Some("EQX-1234".to_string())).unwrap();
+        assert!(handoff.delivered_at.is_some());
+        assert_eq!(handoff.provider_reference.as_deref(), Some("EQX-1234"));
+        assert_eq!(manager.pending_handoffs().len(), 1);
+        assert!(manager.circuit_for(&Uuid::new_v4()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provider_provisioning_and_peering() {
+        let provisioned = json!({
+            "id": CIRCUIT_ID,
+            "name": "opensase-er",
+            "properties": {
+                "serviceKey": "key",
+                "provisioningState": "Succeeded",
+                "circuitProvisioningState": "Enabled",
+                "serviceProviderProvisioningState": "Provisioned",
+                "serviceProviderProperties": { "serviceProviderName": "Equinix", "peeringLocation": "Amsterdam", "bandwidthInMbps": 1000 }
+            }
+        });
+        let (base, log) = arm(vec![
+            ("POST", "/oauth2/v2.0/token", json!({ "access_token": "token", "expires_in": 3600 })),
+            ("GET", "/peerings/AzurePrivatePeering?", json!({ "properties": {
+                "peeringType": "AzurePrivatePeering",
+                "state": "Enabled",
+                "provisioningState": "Succeeded",
+                "azureASN": 12076,
+                "primaryAzurePort": "EQIX-AMS-06GMR-CIS-1-PRI-A"
+            }})),
+            ("PUT", "/peerings/AzurePrivatePeering?", json!({})),
+            ("GET", "/expressRouteCircuits/opensase-er?", provisioned),
+        ]).await;
+        let manager = manager(&base);
+
+        let circuit = manager.wait_for_provider_provisioning("rg-net", "opensase-er").await.unwrap();
+        assert!(circuit.is_provider_provisioned());
+
+        let peering = ExpressRoutePeering::private(&bgp(65010, "10.0.0.1", "10.0.0.2"), 100, "10.0.0.0/30", "10.0.0.4/30").unwrap();
+        let status = manager.configure_private_peering(&circuit, peering.clone()).await.unwrap();
+        assert_eq!((status.state.as_str(), status.azure_asn), ("Enabled", AZURE_ASN));
+        assert_eq!(status.primary_azure_port.as_deref(), Some("EQIX-AMS-06GMR-CIS-1-PRI-A"));
+        assert_eq!(status.secondary_azure_port, None);
+
+        // The token is fetched once and reused
+        let log = log.lock().clone();
+        assert_eq!(log.iter().filter(|l| l.contains("/token")).count(), 1);
+        assert!(log.iter().any(|l| l.starts_with("PUT") && l.contains("/peerings/AzurePrivatePeering")));
+
+        // Peering waits for the provider
+        let pending = er_circuit("Succeeded", "Enabled", "Provisioning");
+        assert!(matches!(
+            manager.configure_private_peering(&pending, peering).await,
+            Err(ConnectorError::ProvisioningError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_health_mapping() {
+        let metrics = json!({ "value": [
+            { "name": { "value": "BgpAvailability" }, "timeseries": [{ "data": [{ "average": 100.0 }, { "average": 100.0 }] }] },
+            { "name": { "value": "ArpAvailability" }, "timeseries": [{ "data": [{ "average": 90.0 }, { "average": 100.0 }, {}] }] }
+        ]});
+        let stats = json!({ "primarybytesIn": 100, "secondarybytesIn": 50, "primarybytesOut": 70, "secondarybytesOut": 30 });
+        let routes = |summary: Value| vec![
+            ("POST", "/oauth2/v2.0/token", json!({ "access_token": "token", "expires_in": 3600 })),
+            ("GET", "/stats?", stats.clone()),
+            ("GET", "/microsoft.insights/metrics?", metrics.clone()),
+            ("POST", "/routeTablesSummary/primary?", summary),
+        ];
+        let config = bgp(65010, "10.0.0.1", "10.0.0.2");
+        let circuit = er_circuit("Succeeded", "Enabled", "Provisioned");
+
+        let (base, _) = arm(routes(json!({ "value": [
+            { "neighbor": "10.0.0.5", "statePfxRcd": "Active" },
+            { "neighbor": "10.0.0.1", "upDown": "1d02h", "statePfxRcd": "4" }
+        ]}))).await;
+        let health = manager(&base).circuit_health(&circuit, &config).await.unwrap();
+        assert_eq!(health.bgp_state, BgpState::Established);
+        assert_eq!((health.bgp_uptime_secs, health.prefixes_advertised, health.prefixes_received), (26 * 3600, 4, 1));
+        assert_eq!((health.rx_bytes, health.tx_bytes), (150, 100));
+        assert!((health.packet_loss - 0.05).abs() < 1e-9);
+
+        // A down session reports its state and no uptime
+        let (base, _) = arm(routes(json!({ "value": [
+            { "neighbor": "10.0.0.1", "upDown": "00:05:00", "statePfxRcd": "Connect" }
+        ]}))).await;
+        let health = manager(&base).circuit_health(&circuit, &config).await.unwrap();
+        assert_eq!((health.bgp_state, health.bgp_uptime_secs, health.prefixes_advertised), (BgpState::Connect, 0, 0));
+
+        // Without our neighbor in the summary, BGP availability decides
+        let (base, _) = arm(routes(json!({ "value": [] }))).await;
+        let health = manager(&base).circuit_health(&circuit, &config).await.unwrap();
+        assert_eq!((health.bgp_state, health.prefixes_advertised), (BgpState::Established, 2));
+    }
+}
@@ -0,0 +1,376 @@
+//! Cloud egress cost awareness
+//!
+//! Models per-GB egress pricing per provider and region, tracks the volume
+//! each connection actually carries from its `ConnectionHealth` counters,
+//! projects the monthly bill, and picks the cheapest connection that still
+//! meets latency/loss limits for traffic that is not latency sensitive.
+
+use crate::{CloudConnection, CloudProvider, ConnectionHealth, ConnectionStatus, ConnectionType};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const BYTES_PER_GB: f64 = 1_000_000_000.0;
+
+/// Pricing region group, as the providers bill egress
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RegionZone {
+    NorthAmerica,
+    Europe,
+    AsiaPacific,
+    SouthAmerica,
+    Other,
+}
+
+impl RegionZone {
+    /// Classify a provider region name (`us-east-1`, `westeurope`, `asia-southeast1`, ...)
+    pub fn classify(region: &str) -> Self {
+        let region = region.to_lowercase();
+        let starts = |prefixes: &[&str]| prefixes.iter().any(|p| region.starts_with(p));
+        let contains = |parts: &[&str]| parts.iter().any(|p| region.contains(p));
+        
+        if starts(&["us-", "ca-", "northamerica-"]) || contains(&["eastus", "westus", "centralus", "canada"]) {
+            Self::NorthAmerica
+        } else if starts(&["eu-", "europe-"]) || contains(&["europe", "uk", "france", "germany", "norway", "sweden", "switzerland"]) {
+            Self::Europe
+        } else if starts(&["ap-", "asia-", "australia-"]) || contains(&["asia", "japan", "korea", "india", "australia"]) {
+            Self::AsiaPacific
+        } else if starts(&["sa-", "southamerica-"]) || contains(&["brazil"]) {
+            Self::SouthAmerica
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Path the egress takes out of the cloud
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EgressKind {
+    /// Dedicated interconnect (Direct Connect, ExpressRoute, Interconnect)
+    Private,
+    /// Internet egress (VPN fallback)
+    Internet,
+}
+
+impl EgressKind {
+    pub fn for_connection(connection: &CloudConnection) -> Self {
+        match connection.connection_type {
+            ConnectionType::VpnTunnel { .. } => Self::Internet,
+            _ => Self::Private,
+        }
+    }
+}
+
+/// One tier of a per-GB price schedule
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PriceTier {
+    /// Monthly volume this tier covers up to; `None` for the last tier
+    pub up_to_gb: Option<f64>,
+    pub usd_per_gb: f64,
+}
+
+/// Tiered egress price schedule
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EgressPricing {
+    pub tiers: Vec<PriceTier>,
+}
+
+impl EgressPricing {
+    /// Single price for all volume
+    pub fn flat(usd_per_gb: f64) -> Self {
+        Self { tiers: vec![PriceTier { up_to_gb: None, usd_per_gb }] }
+    }
+    
+    /// Cost of `gb` gigabytes in one month
+    pub fn cost(&self, gb: f64) -> f64 {
+        let mut remaining = gb.max(0.0);
+        let mut floor = 0.0;
+        let mut cost = 0.0;
+        for tier in &self.tiers {
+            let span = tier.up_to_gb.map_or(f64::INFINITY, |cap| (cap - floor).max(0.0));
+            let billed = remaining.min(span);
+            cost += billed * tier.usd_per_gb;
+            remaining -= billed;
+            if remaining <= 0.0 {
+                break;
+            }
+            floor = tier.up_to_gb.unwrap_or(floor);
+        }
+        cost
+    }
+    
+    /// Price of the next GB after `gb` already billed this month
+    pub fn marginal(&self, gb: f64) -> f64 {
+        self.tiers.iter()
+            .find(|t| t.up_to_gb.is_none_or(|cap| gb < cap))
+            .or(self.tiers.last())
+            .map_or(0.0, |t| t.usd_per_gb)
+    }
+}
+
+/// Egress price table per provider, zone and egress kind
+pub struct EgressPriceBook {
+    prices: RwLock<HashMap<(CloudProvider, RegionZone, EgressKind), EgressPricing>>,
+    /// Region-specific overrides (exact region name)
+    regional: RwLock<HashMap<(CloudProvider, String, EgressKind), EgressPricing>>,
+}
+
+impl EgressPriceBook {
+    /// Empty price book
+    pub fn empty() -> Self {
+        Self {
+            prices: RwLock::new(HashMap::new()),
+            regional: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    /// Price book seeded with list prices (USD, public price pages)
+    pub fn new() -> Self {
+        use EgressKind::*;
+        use RegionZone::*;
+        
+        let book = Self::empty();
+        let internet = |tiers: &[(Option<f64>, f64)]| EgressPricing {
+            tiers: tiers.iter().map(|(up_to_gb, usd_per_gb)| PriceTier { up_to_gb: *up_to_gb, usd_per_gb: *usd_per_gb }).collect(),
+        };
+        
+        // AWS: Direct Connect data transfer out, internet egress tiers
+        let aws_internet = internet(&[(Some(10_240.0), 0.09), (Some(51_200.0), 0.085), (Some(153_600.0), 0.07), (None, 0.05)]);
+        for (zone, dx) in [(NorthAmerica, 0.02), (Europe, 0.02), (AsiaPacific, 0.041), (SouthAmerica, 0.07), (Other, 0.09)] {
+            book.set(CloudProvider::Aws, zone, Private, EgressPricing::flat(dx));
+            book.set(CloudProvider::Aws, zone, Internet, aws_internet.clone());
+        }
+        
+        // Azure: ExpressRoute metered zones, internet egress
+        let azure_internet = internet(&[(Some(10_240.0), 0.087), (Some(51_200.0), 0.083), (Some(153_600.0), 0.07), (None, 0.05)]);
+        for (zone, er) in [(NorthAmerica, 0.025), (Europe, 0.025), (AsiaPacific, 0.05), (SouthAmerica, 0.14), (Other, 0.14)] {
+            book.set(CloudProvider::Azure, zone, Private, EgressPricing::flat(er));
+            book.set(CloudProvider::Azure, zone, Internet, azure_internet.clone());
+        }
+        
+        // GCP: Interconnect egress, premium tier internet egress
+        let gcp_internet = internet(&[(Some(1_024.0), 0.12), (Some(10_240.0), 0.11), (None, 0.08)]);
+        for (zone, ic) in [(NorthAmerica, 0.02), (Europe, 0.02), (AsiaPacific, 0.05), (SouthAmerica, 0.06), (Other, 0.06)] {
+            book.set(CloudProvider::Gcp, zone, Private, EgressPricing::flat(ic));
+            book.set(CloudProvider::Gcp, zone, Internet, gcp_internet.clone());
+        }
+        
+        book
+    }
+    
+    /// Set price for a provider zone
+    pub fn set(&self, provider: CloudProvider, zone: RegionZone, kind: EgressKind, pricing: EgressPricing) {
+        self.prices.write().insert((provider, zone, kind), pricing);
+    }
+    
+    /// Override price for one region (e.g. negotiated rates)
+    pub fn set_regional(&self, provider: CloudProvider, region: &str, kind: EgressKind, pricing: EgressPricing) {
+        self.regional.write().insert((provider, region.to_lowercase(), kind), pricing);
+    }
+    
+    /// Pricing for a connection's egress, if known
+    pub fn pricing_for(&self, connection: &CloudConnection) -> Option<EgressPricing> {
+        let kind = EgressKind::for_connection(connection);
+        let provider = connection.cloud_provider;
+        let region = connection.cloud_region.to_lowercase();
+        
+        if let Some(pricing) = self.regional.read().get(&(provider, region, kind)) {
+            return Some(pricing.clone());
+        }
+        self.prices.read()
+            .get(&(provider, RegionZone::classify(&connection.cloud_region), kind))
+            .cloned()
+    }
+}
+
+impl Default for EgressPriceBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Month-to-date egress volume for one connection
+#[derive(Clone, Debug)]
+struct VolumeState {
+    month: NaiveDate,
+    month_bytes: u64,
+    last_counter: Option<u64>,
+    first_sample: DateTime<Utc>,
+}
+
+/// Tracks egress volume per connection from health counters
+pub struct CostTracker {
+    prices: EgressPriceBook,
+    volumes: RwLock<HashMap<Uuid, VolumeState>>,
+}
+
+impl CostTracker {
+    pub fn new(prices: EgressPriceBook) -> Self {
+        Self { prices, volumes: RwLock::new(HashMap::new()) }
+    }
+    
+    /// Price book in use
+    pub fn prices(&self) -> &EgressPriceBook {
+        &self.prices
+    }
+    
+    /// Fold a health sample into the connection's month-to-date volume
+    ///
+    /// Cloud egress is what the cloud sends toward the PoP, i.e. the PoP's
+    /// `rx_bytes`. Counter resets (router restart) count the new value as
+    /// fresh volume.
+    pub fn record(&self, connection_id: Uuid, health: &ConnectionHealth) {
+        let at = health.last_checked;
+        let month = month_start(at);
+        let mut volumes = self.volumes.write();
+        let state = volumes.entry(connection_id).or_insert_with(|| VolumeState {
+            month,
+            month_bytes: 0,
+            last_counter: None,
+            first_sample: at,
+        });
+        
+        if state.month != month {
+            state.month = month;
+            state.month_bytes = 0;
+            state.first_sample = at;
+        }
+        let delta = match state.last_counter {
+            Some(last) if health.rx_bytes >= last => health.rx_bytes - last,
+            Some(_) => health.rx_bytes,
+            None => 0,
+        };
+        state.month_bytes += delta;
+        state.last_counter = Some(health.rx_bytes);
+    }
+    
+    /// Forget a deleted connection
+    pub fn remove(&self, connection_id: &Uuid) {
+        self.volumes.write().remove(connection_id);
+    }
+    
+    /// Gigabytes egressed this month
+    pub fn month_to_date_gb(&self, connection_id: &Uuid) -> f64 {
+        self.volumes.read()
+            .get(connection_id)
+            .filter(|v| v.month == month_start(Utc::now()))
+            .map_or(0.0, |v| v.month_bytes as f64 / BYTES_PER_GB)
+    }
+    
+    /// Cost projection for a connection at `now`
+    pub fn project(&self, connection: &CloudConnection, now: DateTime<Utc>) -> CostProjection {
+        let pricing = self.prices.pricing_for(connection);
+        let month = month_start(now);
+        let (gb, since) = self.volumes.read()
+            .get(&connection.id)
+            .filter(|v| v.month == month)
+            .map_or((0.0, now), |v| (v.month_bytes as f64 / BYTES_PER_GB, v.first_sample));
+        
+        // Extrapolate from the observed window to the whole month
+        let month_secs = days_in_month(month) as f64 * 86_400.0;
+        let observed_secs = (now - since).num_seconds().max(3_600) as f64;
+        let projected_gb = gb * (month_secs / observed_secs).max(1.0);
+        
+        CostProjection {
+            connection_id: connection.id,
+            connection_name: connection.name.clone(),
+            provider: connection.cloud_provider,
+            region: connection.cloud_region.clone(),
+            egress_kind: EgressKind::for_connection(connection),
+            month,
+            month_to_date_gb: gb,
+            month_to_date_usd: pricing.as_ref().map_or(0.0, |p| p.cost(gb)),
+            projected_gb,
+            projected_usd: pricing.as_ref().map_or(0.0, |p| p.cost(projected_gb)),
+            marginal_usd_per_gb: pricing.as_ref().map(|p| p.marginal(gb)),
+        }
+    }
+    
+    /// Cheapest connection that meets the policy's health limits
+    ///
+    /// Ranks by the marginal price of the next GB, so a connection that
+    /// has already crossed into a cheaper volume tier wins.
+    pub fn cheapest_viable<'a>(
+        &self,
+        connections: &'a [CloudConnection],
+        policy: &CostSteeringPolicy,
+    ) -> Option<&'a CloudConnection> {
+        connections.iter()
+            .filter(|c| policy.is_viable(c))
+            .filter_map(|c| {
+                let price = self.prices.pricing_for(c)?.marginal(self.month_to_date_gb(&c.id));
+                Some((c, price))
+            })
+            .min_by(|(a, pa), (b, pb)| {
+                pa.total_cmp(pb).then(a.health.latency_ms.total_cmp(&b.health.latency_ms))
+            })
+            .map(|(c, _)| c)
+    }
+}
+
+impl Default for CostTracker {
+    fn default() -> Self {
+        Self::new(EgressPriceBook::new())
+    }
+}
+
+/// Limits a connection must meet to carry steered bulk traffic
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CostSteeringPolicy {
+    pub max_latency_ms: f64,
+    pub max_packet_loss: f64,
+    /// Only consider connections to these providers (empty = any)
+    pub providers: Vec<CloudProvider>,
+}
+
+impl Default for CostSteeringPolicy {
+    fn default() -> Self {
+        Self {
+            max_latency_ms: 150.0,
+            max_packet_loss: 0.01,
+            providers: Vec::new(),
+        }
+    }
+}
+
+impl CostSteeringPolicy {
+    pub fn is_viable(&self, connection: &CloudConnection) -> bool {
+        connection.status == ConnectionStatus::Active
+            && connection.health.latency_ms <= self.max_latency_ms
+            && connection.health.packet_loss <= self.max_packet_loss
+            && (self.providers.is_empty() || self.providers.contains(&connection.cloud_provider))
+    }
+}
+
+/// Monthly egress cost projection for one connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CostProjection {
+    pub connection_id: Uuid,
+    pub connection_name: String,
+    pub provider: CloudProvider,
+    pub region: String,
+    pub egress_kind: EgressKind,
+    pub month: NaiveDate,
+    pub month_to_date_gb: f64,
+    pub month_to_date_usd: f64,
+    pub projected_gb: f64,
+    pub projected_usd: f64,
+    /// `None` when no price is known for the provider/region
+    pub marginal_usd_per_gb: Option<f64>,
+}
+
+fn month_start(at: DateTime<Utc>) -> NaiveDate {
+    let date = at.date_naive();
+    date.with_day(1).unwrap_or(date)
+}
+
+fn days_in_month(month: NaiveDate) -> i64 {
+    let next = if month.month() == 12 {
+        NaiveDate::from_ymd_opt(month.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(month.year(), month.month() + 1, 1)
+    };
+    next.map_or(30, |n| (n - month).num_days())
+}
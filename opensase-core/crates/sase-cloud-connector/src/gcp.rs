@@ -1,13 +1,63 @@
 //! GCP Cloud Interconnect integration
 //!
 //! Provides connectivity via GCP Dedicated Interconnect and Partner Interconnect.
+//!
+//! Resources are managed through the Compute Engine REST API. Every cloud
+//! connection gets a redundant pair of VLAN attachments in separate edge
+//! availability domains, terminated on one Cloud Router. Partner
+//! attachments issue pairing keys and wait for the partner to provision;
+//! Dedicated attachments ride on our own interconnects, which can run
+//! MACsec. Once the attachments are up, a BGP session per attachment is
+//! added to the Cloud Router and its status is polled into
+//! `ConnectionHealth`.
 
-use crate::ConnectorError;
+use crate::{
+    BgpConfig, BgpState, CloudConnection, CloudConnectorService, ConnectionHealth, ConnectionStatus,
+    ConnectionType, ConnectorError, GcpInterconnectType,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+const COMPUTE_ENDPOINT: &str = "https://compute.googleapis.com/compute/v1";
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Cloud Router ASN Google requires for Partner Interconnect
+pub const PARTNER_ROUTER_ASN: u32 = 16550;
+
+/// VLAN attachment capacities, smallest first
+const ATTACHMENT_BANDWIDTHS: [(u32, &str); 13] = [
+    (50, "BPS_50M"), (100, "BPS_100M"), (200, "BPS_200M"), (300, "BPS_300M"),
+    (400, "BPS_400M"), (500, "BPS_500M"), (1_000, "BPS_1G"), (2_000, "BPS_2G"),
+    (5_000, "BPS_5G"), (10_000, "BPS_10G"), (20_000, "BPS_20G"), (50_000, "BPS_50G"),
+    (100_000, "BPS_100G"),
+];
+
+/// Project and token used for Compute Engine calls
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GcpCredentials {
+    pub project: String,
+    /// OAuth access token; when unset a token is fetched from the metadata server
+    pub access_token: Option<String>,
+}
 
 /// GCP Cloud Interconnect manager
 pub struct GcpConnectorManager {
-    // GCP SDK client would go here
+    http: reqwest::Client,
+    credentials: Option<GcpCredentials>,
+    compute_endpoint: String,
+    /// Cached metadata-server token and its expiry
+    token: parking_lot::Mutex<Option<(String, DateTime<Utc>)>>,
+    /// Interval between attachment and operation polls
+    poll_interval: Duration,
+    /// How long to wait for attachments to be provisioned
+    provisioning_timeout: Duration,
+    /// Attachment pairs by cloud connection id
+    attachments: dashmap::DashMap<Uuid, AttachmentPair>,
 }
 
 /// GCP Interconnect location
@@ -23,12 +73,31 @@ pub struct InterconnectLocation {
 /// GCP Interconnect details
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Interconnect {
+    /// Resource URL (selfLink)
     pub id: String,
     pub name: String,
     pub interconnect_type: InterconnectType,
     pub link_type: String,
     pub location: String,
+    /// ACTIVE or UNPROVISIONED
     pub state: String,
+    /// OS_ACTIVE or OS_UNPROVISIONED
+    pub operational_status: String,
+    pub requested_link_count: u32,
+    pub provisioned_link_count: u32,
+    /// IF_MACSEC when the ports support MACsec
+    pub available_features: Vec<String>,
+    pub macsec_enabled: bool,
+}
+
+impl Interconnect {
+    pub fn is_active(&self) -> bool {
+        self.state == "ACTIVE" && self.operational_status == "OS_ACTIVE"
+    }
+    
+    pub fn supports_macsec(&self) -> bool {
+        self.available_features.iter().any(|f| f == "IF_MACSEC")
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -37,21 +106,219 @@ pub enum InterconnectType {
     Partner,
 }
 
+/// Dedicated Interconnect to order
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InterconnectSpec {
+    pub name: String,
+    /// Interconnect location, e.g. `iad-zone1-1`
+    pub location: String,
+    /// e.g. `LINK_TYPE_ETHERNET_10G_LR`
+    pub link_type: String,
+    pub requested_link_count: u32,
+    /// Name printed on the LOA-CFA
+    pub customer_name: String,
+    /// Request MACsec-capable ports and enable MACsec with these keys
+    pub macsec: Option<MacsecSettings>,
+}
+
+/// MACsec configuration for a Dedicated Interconnect
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MacsecSettings {
+    pub keys: Vec<MacsecKey>,
+    /// Keep passing unencrypted traffic if the MKA session fails
+    pub fail_open: bool,
+}
+
+/// Pre-shared key slot; Google generates the CAK/CKN for each
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MacsecKey {
+    pub name: String,
+    /// When the key becomes valid; immediately when unset
+    pub start_time: Option<DateTime<Utc>>,
+}
+
+/// Generated MACsec key material to install on our routers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MacsecPreSharedKey {
+    pub name: String,
+    pub cak: String,
+    pub ckn: String,
+    pub start_time: Option<String>,
+}
+
+/// Edge availability domain of a VLAN attachment
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeAvailabilityDomain {
+    Domain1,
+    Domain2,
+}
+
+impl EdgeAvailabilityDomain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Domain1 => "AVAILABILITY_DOMAIN_1",
+            Self::Domain2 => "AVAILABILITY_DOMAIN_2",
+        }
+    }
+    
+    /// Domain of an interconnect location (`iad-zone1-1` is domain 1)
+    pub fn of_location(location: &str) -> Option<Self> {
+        let name = location.rsplit('/').next().unwrap_or(location);
+        if name.contains("zone1") {
+            Some(Self::Domain1)
+        } else if name.contains("zone2") {
+            Some(Self::Domain2)
+        } else {
+            None
+        }
+    }
+}
+
 /// GCP Interconnect attachment
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InterconnectAttachment {
+    /// Resource URL (selfLink)
     pub id: String,
     pub name: String,
+    /// Cloud Router URL
     pub router: String,
     pub region: String,
+    pub attachment_type: GcpInterconnectType,
+    /// 802.1Q tag; assigned by the partner for Partner attachments
     pub vlan_tag: u16,
+    /// Key handed to the partner (Partner only)
     pub pairing_key: Option<String>,
+    pub edge_availability_domain: String,
+    /// ACTIVE, PENDING_PARTNER, PARTNER_REQUEST_RECEIVED, PENDING_CUSTOMER,
+    /// UNPROVISIONED or DEFUNCT
     pub state: String,
+    pub operational_status: String,
+    /// Cloud Router side of the link, CIDR notation
+    pub cloud_router_ip: Option<String>,
+    /// Our side of the link, CIDR notation
+    pub customer_router_ip: Option<String>,
+    pub partner_name: Option<String>,
+}
+
+impl InterconnectAttachment {
+    /// Lifecycle status of this attachment alone
+    pub fn connection_status(&self) -> ConnectionStatus {
+        match self.state.as_str() {
+            "ACTIVE" if self.operational_status == "OS_ACTIVE" => ConnectionStatus::Active,
+            "ACTIVE" | "PENDING_CUSTOMER" => ConnectionStatus::Provisioning,
+            "PENDING_PARTNER" | "PARTNER_REQUEST_RECEIVED" => ConnectionStatus::Pending,
+            "UNPROVISIONED" | "DEFUNCT" => ConnectionStatus::Down,
+            _ => ConnectionStatus::Provisioning,
+        }
+    }
+    
+    /// Partner has provisioned and is waiting for us to activate
+    pub fn awaiting_activation(&self) -> bool {
+        self.state == "PENDING_CUSTOMER"
+    }
+}
+
+/// Redundant pair of attachments backing one cloud connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttachmentPair {
+    /// Cloud Router URL
+    pub router: String,
+    pub primary: InterconnectAttachment,
+    pub secondary: InterconnectAttachment,
+}
+
+impl AttachmentPair {
+    pub fn attachments(&self) -> [&InterconnectAttachment; 2] {
+        [&self.primary, &self.secondary]
+    }
+    
+    /// Active with both links up, degraded on one; otherwise the state of
+    /// the link furthest from service
+    pub fn connection_status(&self) -> ConnectionStatus {
+        let primary = self.primary.connection_status();
+        let secondary = self.secondary.connection_status();
+        match (primary, secondary) {
+            (ConnectionStatus::Active, ConnectionStatus::Active) => ConnectionStatus::Active,
+            (ConnectionStatus::Active, _) | (_, ConnectionStatus::Active) => ConnectionStatus::Degraded,
+            _ => [ConnectionStatus::Down, ConnectionStatus::Pending]
+                .into_iter()
+                .find(|s| *s == primary || *s == secondary)
+                .unwrap_or(ConnectionStatus::Provisioning),
+        }
+    }
+}
+
+/// Settings for provisioning a Cloud Interconnect cloud connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudInterconnectProvisioning {
+    /// VPC network served by the Cloud Router
+    pub network: String,
+    /// Cloud Router to create or reuse
+    pub router: String,
+    /// Dedicated only: interconnects for each attachment, in different
+    /// edge availability domains
+    pub primary_interconnect: Option<String>,
+    pub secondary_interconnect: Option<String>,
+    /// Dedicated only: link-local /29 for each attachment; Google picks
+    /// one when unset
+    pub primary_candidate_subnet: Option<String>,
+    pub secondary_candidate_subnet: Option<String>,
+}
+
+/// BGP session configured on the Cloud Router for one attachment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InterconnectBgpSession {
+    pub attachment: String,
+    pub interface_name: String,
+    pub peer_name: String,
+    pub cloud_router_ip: IpAddr,
+    pub peer_ip: IpAddr,
+    pub peer_asn: u32,
+}
+
+/// BGP peer status reported by the Cloud Router
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RouterPeerStatus {
+    pub name: String,
+    pub peer_ip: String,
+    pub state: BgpState,
+    pub uptime_secs: u64,
+    pub learned_routes: u32,
+    pub advertised_routes: u32,
 }
 
 impl GcpConnectorManager {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            http: reqwest::Client::new(),
+            credentials: None,
+            compute_endpoint: COMPUTE_ENDPOINT.to_string(),
+            token: parking_lot::Mutex::new(None),
+            poll_interval: Duration::from_secs(30),
+            provisioning_timeout: Duration::from_secs(14 * 24 * 3600),
+            attachments: dashmap::DashMap::new(),
+        }
+    }
+    
+    pub fn with_credentials(mut self, credentials: GcpCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+    
+    /// Override the Compute Engine endpoint
+    pub fn with_endpoint(mut self, compute: &str) -> Self {
+        self.compute_endpoint = compute.trim_end_matches('/').to_string();
+        self
+    }
+    
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    
+    pub fn with_provisioning_timeout(mut self, timeout: Duration) -> Self {
+        self.provisioning_timeout = timeout;
+        self
     }
     
     /// List available interconnect locations
@@ -81,69 +348,607 @@ impl GcpConnectorManager {
         ])
     }
     
-    /// Create dedicated interconnect
-    pub async fn create_dedicated_interconnect(
+    /// Order a Dedicated Interconnect, requesting MACsec-capable ports when
+    /// the spec carries MACsec keys
+    pub async fn create_dedicated_interconnect(&self, spec: &InterconnectSpec) -> Result<Interconnect, ConnectorError> {
+        if spec.requested_link_count == 0 {
+            return Err(ConnectorError::ValidationError("At least one link is required".to_string()));
+        }
+        
+        let mut body = json!({
+            "name": spec.name,
+            "location": self.global_url(&format!("interconnectLocations/{}", spec.location))?,
+            "interconnectType": "DEDICATED",
+            "linkType": spec.link_type,
+            "requestedLinkCount": spec.requested_link_count,
+            "customerName": spec.customer_name,
+            "adminEnabled": true,
+        });
+        if let Some(macsec) = &spec.macsec {
+            body["requestedFeatures"] = json!(["IF_MACSEC"]);
+            body["macsecEnabled"] = json!(true);
+            body["macsec"] = macsec_body(macsec)?;
+        }
+        
+        let operation = self.send(reqwest::Method::POST, &self.global_url("interconnects")?, Some(body)).await?;
+        self.wait_for_operation(operation).await?;
+        
+        let interconnect = self.get_interconnect(&spec.name).await?;
+        tracing::info!(
+            "Ordered Dedicated Interconnect {} at {} ({} x {}); LOA-CFA pending",
+            interconnect.name, spec.location, spec.requested_link_count, spec.link_type
+        );
+        Ok(interconnect)
+    }
+    
+    /// Fetch an interconnect's current state
+    pub async fn get_interconnect(&self, name: &str) -> Result<Interconnect, ConnectorError> {
+        let body = self.send(reqwest::Method::GET, &self.global_url(&format!("interconnects/{}", name))?, None).await?;
+        parse_interconnect(&body)
+    }
+    
+    /// Enable MACsec on a Dedicated Interconnect, replacing its key slots.
+    /// Adding a key with a later start time rotates to it when it starts.
+    pub async fn configure_macsec(
         &self,
-        project: &str,
-        name: &str,
-        location: &str,
-        link_type: &str,
+        interconnect: &Interconnect,
+        settings: &MacsecSettings,
     ) -> Result<Interconnect, ConnectorError> {
-        Ok(Interconnect {
-            id: format!("projects/{}/global/interconnects/{}", project, name),
-            name: name.to_string(),
-            interconnect_type: InterconnectType::Dedicated,
-            link_type: link_type.to_string(),
-            location: location.to_string(),
-            state: "PENDING_LOA".to_string(),
-        })
+        if matches!(interconnect.interconnect_type, InterconnectType::Partner) {
+            return Err(ConnectorError::ValidationError("MACsec is only available on Dedicated Interconnect".to_string()));
+        }
+        if !interconnect.supports_macsec() {
+            return Err(ConnectorError::ProvisioningError(format!(
+                "Interconnect {} was not provisioned on MACsec-capable ports", interconnect.name
+            )));
+        }
+        
+        let body = json!({
+            "macsecEnabled": true,
+            "macsec": macsec_body(settings)?,
+        });
+        let operation = self.send(reqwest::Method::PATCH, &interconnect.id, Some(body)).await?;
+        self.wait_for_operation(operation).await?;
+        
+        self.get_interconnect(&interconnect.name).await
     }
     
-    /// Create partner interconnect attachment (VLAN attachment)
-    pub async fn create_partner_attachment(
-        &self,
-        project: &str,
-        region: &str,
-        name: &str,
-        router: &str,
-    ) -> Result<InterconnectAttachment, ConnectorError> {
-        Ok(InterconnectAttachment {
-            id: format!("projects/{}/regions/{}/interconnectAttachments/{}", project, region, name),
-            name: name.to_string(),
-            router: router.to_string(),
-            region: region.to_string(),
-            vlan_tag: 0,
-            pairing_key: Some(format!("{}/{}/{}/{}", 
-                uuid::Uuid::new_v4().to_string()[..8].to_string(),
-                region, "1", "2")),
-            state: "PENDING_PARTNER".to_string(),
-        })
+    /// CAK/CKN pairs Google generated for the interconnect's key slots
+    pub async fn macsec_keys(&self, interconnect: &Interconnect) -> Result<Vec<MacsecPreSharedKey>, ConnectorError> {
+        let body = self.send(reqwest::Method::GET, &format!("{}/getMacsecConfig", interconnect.id), None).await?;
+        Ok(body["result"]["preSharedKeys"].as_array().into_iter().flatten()
+            .map(|key| MacsecPreSharedKey {
+                name: str_field(key, "name"),
+                cak: str_field(key, "cak"),
+                ckn: str_field(key, "ckn"),
+                start_time: key["startTime"].as_str().map(str::to_string),
+            })
+            .collect())
     }
     
-    /// Create Cloud Router
-    pub async fn create_cloud_router(
+    /// Create a Cloud Router, or reuse an existing one with the same ASN
+    pub async fn ensure_cloud_router(
         &self,
-        project: &str,
         region: &str,
         name: &str,
         network: &str,
         asn: u32,
     ) -> Result<String, ConnectorError> {
-        let router_id = format!("projects/{}/regions/{}/routers/{}", project, region, name);
-        Ok(router_id)
+        let url = self.region_url(region, &format!("routers/{}", name))?;
+        if let Some(router) = self.get_optional(&url).await? {
+            let existing_asn = router["bgp"]["asn"].as_u64().unwrap_or(0) as u32;
+            if existing_asn != asn {
+                return Err(ConnectorError::BgpError(format!(
+                    "Cloud Router {} uses ASN {}, expected {}", name, existing_asn, asn
+                )));
+            }
+            return Ok(str_field(&router, "selfLink"));
+        }
+        
+        let body = json!({
+            "name": name,
+            "network": self.global_url(&format!("networks/{}", network))?,
+            "bgp": {
+                "asn": asn,
+                "advertiseMode": "DEFAULT",
+            },
+        });
+        let operation = self.send(reqwest::Method::POST, &self.region_url(region, "routers")?, Some(body)).await?;
+        self.wait_for_operation(operation).await?;
+        
+        let router = self.send(reqwest::Method::GET, &url, None).await?;
+        Ok(str_field(&router, "selfLink"))
     }
     
-    /// Add BGP peer to router
-    pub async fn add_bgp_peer(
+    /// Create a redundant pair of VLAN attachments. Partner attachments are
+    /// placed in both edge availability domains; Dedicated attachments
+    /// must sit on interconnects in different domains.
+    pub async fn create_attachment_pair(
         &self,
-        router_id: &str,
-        peer_name: &str,
-        peer_asn: u32,
-        peer_ip: &str,
-        interface_name: &str,
-    ) -> Result<(), ConnectorError> {
-        // In production: call GCP API
-        Ok(())
+        connection: &CloudConnection,
+        router: &str,
+        options: &CloudInterconnectProvisioning,
+    ) -> Result<AttachmentPair, ConnectorError> {
+        let ConnectionType::CloudInterconnect { interconnect_type } = &connection.connection_type else {
+            return Err(ConnectorError::ValidationError(format!("{} is not a Cloud Interconnect connection", connection.name)));
+        };
+        let region = &connection.cloud_region;
+        
+        let bodies = match interconnect_type {
+            GcpInterconnectType::Partner => [EdgeAvailabilityDomain::Domain1, EdgeAvailabilityDomain::Domain2]
+                .map(|domain| json!({
+                    "type": "PARTNER",
+                    "router": router,
+                    "edgeAvailabilityDomain": domain.as_str(),
+                    // Pre-activate so the partner's provisioning brings it up
+                    "adminEnabled": true,
+                })),
+            GcpInterconnectType::Dedicated => {
+                let (Some(primary), Some(secondary)) = (&options.primary_interconnect, &options.secondary_interconnect) else {
+                    return Err(ConnectorError::ValidationError(
+                        "Dedicated attachments need a primary and a secondary interconnect".to_string(),
+                    ));
+                };
+                let primary = self.get_interconnect(primary).await?;
+                let secondary = self.get_interconnect(secondary).await?;
+                let domains = (
+                    EdgeAvailabilityDomain::of_location(&primary.location),
+                    EdgeAvailabilityDomain::of_location(&secondary.location),
+                );
+                if primary.id == secondary.id || domains.0.is_some() && domains.0 == domains.1 {
+                    return Err(ConnectorError::ValidationError(format!(
+                        "Interconnects {} and {} are not in separate edge availability domains",
+                        primary.name, secondary.name
+                    )));
+                }
+                
+                let bandwidth = attachment_bandwidth(connection.bandwidth_mbps)?;
+                [(&primary, &options.primary_candidate_subnet), (&secondary, &options.secondary_candidate_subnet)]
+                    .map(|(interconnect, subnet)| {
+                        let mut body = json!({
+                            "type": "DEDICATED",
+                            "router": router,
+                            "interconnect": interconnect.id,
+                            "vlanTag8021q": connection.vlan_id,
+                            "bandwidth": bandwidth,
+                            "adminEnabled": true,
+                        });
+                        if let Some(subnet) = subnet {
+                            body["candidateSubnets"] = json!([subnet]);
+                        }
+                        body
+                    })
+            }
+        };
+        
+        let mut created = Vec::with_capacity(2);
+        for (suffix, mut body) in ["primary", "secondary"].into_iter().zip(bodies) {
+            let attachment_name = format!("opensase-{}-{}", connection.id, suffix);
+            body["name"] = json!(attachment_name);
+            let operation = self.send(
+                reqwest::Method::POST,
+                &self.region_url(region, "interconnectAttachments")?,
+                Some(body),
+            ).await?;
+            self.wait_for_operation(operation).await?;
+            created.push(self.get_attachment(region, &attachment_name).await?);
+        }
+        
+        let secondary = created.pop().expect("two attachments created");
+        let primary = created.pop().expect("two attachments created");
+        Ok(AttachmentPair { router: router.to_string(), primary, secondary })
+    }
+    
+    /// Fetch an attachment's current state
+    pub async fn get_attachment(&self, region: &str, name: &str) -> Result<InterconnectAttachment, ConnectorError> {
+        let url = self.region_url(region, &format!("interconnectAttachments/{}", name))?;
+        let body = self.send(reqwest::Method::GET, &url, None).await?;
+        parse_attachment(region, &body)
+    }
+    
+    /// Accept a Partner attachment the partner has provisioned
+    pub async fn activate_attachment(&self, attachment: &InterconnectAttachment) -> Result<InterconnectAttachment, ConnectorError> {
+        let operation = self.send(reqwest::Method::PATCH, &attachment.id, Some(json!({ "adminEnabled": true }))).await?;
+        self.wait_for_operation(operation).await?;
+        self.get_attachment(&attachment.region, &attachment.name).await
+    }
+    
+    /// Poll until the attachment is ready for BGP, activating Partner
+    /// attachments once the partner hands them back
+    pub async fn wait_for_attachment(&self, attachment: &InterconnectAttachment) -> Result<InterconnectAttachment, ConnectorError> {
+        let deadline = tokio::time::Instant::now() + self.provisioning_timeout;
+        
+        loop {
+            let current = self.get_attachment(&attachment.region, &attachment.name).await?;
+            match current.state.as_str() {
+                "ACTIVE" if current.cloud_router_ip.is_some() => return Ok(current),
+                "PENDING_CUSTOMER" => {
+                    tracing::info!("Activating attachment {} provisioned by {}", current.name,
+                        current.partner_name.as_deref().unwrap_or("partner"));
+                    self.activate_attachment(&current).await?;
+                    continue;
+                }
+                "UNPROVISIONED" | "DEFUNCT" => {
+                    return Err(ConnectorError::ProvisioningError(format!(
+                        "Attachment {} is {}", current.name, current.state
+                    )));
+                }
+                state => {
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(ConnectorError::ProvisioningError(format!(
+                            "Attachment {} still {} after {}s", current.name, state, self.provisioning_timeout.as_secs()
+                        )));
+                    }
+                    tracing::debug!("Attachment {} state {}", current.name, state);
+                }
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+    
+    /// Add an interface and BGP peer for each attachment to the Cloud
+    /// Router. Interfaces a Layer 3 partner already created are reused.
+    pub async fn configure_bgp(
+        &self,
+        pair: &AttachmentPair,
+        bgp: &BgpConfig,
+    ) -> Result<Vec<InterconnectBgpSession>, ConnectorError> {
+        let router = self.send(reqwest::Method::GET, &pair.router, None).await?;
+        let mut interfaces = router["interfaces"].as_array().cloned().unwrap_or_default();
+        let mut peers = router["bgpPeers"].as_array().cloned().unwrap_or_default();
+        let mut md5_keys = router["md5AuthenticationKeys"].as_array().cloned().unwrap_or_default();
+        
+        let mut sessions = Vec::with_capacity(2);
+        for attachment in pair.attachments() {
+            let (Some(cloud_ip), Some(peer_ip)) = (&attachment.cloud_router_ip, &attachment.customer_router_ip) else {
+                return Err(ConnectorError::ProvisioningError(format!(
+                    "Attachment {} has no link addresses yet", attachment.name
+                )));
+            };
+            
+            let interface_name = interfaces.iter()
+                .find(|i| i["linkedInterconnectAttachment"].as_str() == Some(attachment.id.as_str()))
+                .map(|i| str_field(i, "name"))
+                .unwrap_or_else(|| {
+                    let name = format!("{}-if", attachment.name);
+                    interfaces.push(json!({
+                        "name": name,
+                        "linkedInterconnectAttachment": attachment.id,
+                        "ipRange": cloud_ip,
+                    }));
+                    name
+                });
+            
+            let peer_name = format!("{}-peer", attachment.name);
+            let mut peer = json!({
+                "name": peer_name,
+                "interfaceName": interface_name,
+                "ipAddress": strip_prefix(cloud_ip),
+                "peerIpAddress": strip_prefix(peer_ip),
+                "peerAsn": bgp.our_asn,
+                "advertisedRoutePriority": bgp.med,
+            });
+            if let Some(key) = &bgp.md5_auth {
+                let key_name = format!("{}-md5", attachment.name);
+                md5_keys.retain(|k| k["name"].as_str() != Some(key_name.as_str()));
+                md5_keys.push(json!({ "name": key_name, "key": key }));
+                peer["md5AuthenticationKeyName"] = json!(key_name);
+            }
+            peers.retain(|p| p["interfaceName"].as_str() != Some(interface_name.as_str()));
+            peers.push(peer);
+            
+            sessions.push(InterconnectBgpSession {
+                attachment: attachment.name.clone(),
+                interface_name,
+                peer_name,
+                cloud_router_ip: parse_link_ip(cloud_ip)?,
+                peer_ip: parse_link_ip(peer_ip)?,
+                peer_asn: bgp.our_asn,
+            });
+        }
+        
+        let patch = json!({
+            "interfaces": interfaces,
+            "bgpPeers": peers,
+            "md5AuthenticationKeys": md5_keys,
+            "fingerprint": router["fingerprint"],
+        });
+        let operation = self.send(reqwest::Method::PATCH, &pair.router, Some(patch)).await?;
+        self.wait_for_operation(operation).await?;
+        
+        Ok(sessions)
+    }
+    
+    /// BGP peer status from the Cloud Router
+    pub async fn router_status(&self, router: &str) -> Result<Vec<RouterPeerStatus>, ConnectorError> {
+        let body = self.send(reqwest::Method::GET, &format!("{}/getRouterStatus", router), None).await?;
+        Ok(body["result"]["bgpPeerStatus"].as_array().into_iter().flatten()
+            .map(|peer| {
+                let state = match peer["status"].as_str() {
+                    Some("UP") => BgpState::Established,
+                    _ => parse_bgp_state(peer["state"].as_str().unwrap_or_default()),
+                };
+                RouterPeerStatus {
+                    name: str_field(peer, "name"),
+                    peer_ip: str_field(peer, "peerIpAddress"),
+                    state,
+                    uptime_secs: peer["uptimeSeconds"].as_str()
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(0),
+                    learned_routes: peer["numLearnedRoutes"].as_u64().unwrap_or(0) as u32,
+                    advertised_routes: peer["advertisedRoutes"].as_array().map(|r| r.len()).unwrap_or(0) as u32,
+                }
+            })
+            .collect())
+    }
+    
+    /// Health of a connection's BGP sessions. The longest-established
+    /// session stands for the connection. Traffic counters are not exposed
+    /// by the Compute API and stay at zero.
+    pub async fn interconnect_health(&self, pair: &AttachmentPair) -> Result<ConnectionHealth, ConnectorError> {
+        let statuses = self.router_status(&pair.router).await?;
+        let names: Vec<String> = pair.attachments().iter().map(|a| format!("{}-peer", a.name)).collect();
+        let ours: Vec<&RouterPeerStatus> = statuses.iter()
+            .filter(|s| names.contains(&s.name))
+            .collect();
+        
+        let established = ours.iter()
+            .filter(|s| s.state == BgpState::Established)
+            .max_by_key(|s| s.uptime_secs);
+        let (bgp_state, uptime) = match established {
+            Some(session) => (BgpState::Established, session.uptime_secs),
+            None => (ours.first().map(|s| s.state).unwrap_or(BgpState::Idle), 0),
+        };
+        
+        Ok(ConnectionHealth {
+            bgp_state,
+            bgp_uptime_secs: uptime,
+            // The Cloud Router's view: what it advertises is what we receive
+            prefixes_received: ours.iter().map(|s| s.advertised_routes).max().unwrap_or(0),
+            prefixes_advertised: ours.iter().map(|s| s.learned_routes).max().unwrap_or(0),
+            rx_bytes: 0,
+            tx_bytes: 0,
+            rx_packets: 0,
+            tx_packets: 0,
+            errors: 0,
+            latency_ms: 0.0,
+            jitter_ms: 0.0,
+            packet_loss: 0.0,
+            last_checked: Utc::now(),
+        })
+    }
+    
+    /// Create the Cloud Router and attachment pair for a Cloud Interconnect
+    /// connection. Partner attachments come back with pairing keys to hand
+    /// to the partner.
+    pub async fn provision_interconnect(
+        &self,
+        service: &CloudConnectorService,
+        connection_id: &Uuid,
+        options: &CloudInterconnectProvisioning,
+    ) -> Result<AttachmentPair, ConnectorError> {
+        let connection = service.get_connection(connection_id)
+            .ok_or(ConnectorError::ConnectionNotFound(*connection_id))?;
+        let ConnectionType::CloudInterconnect { interconnect_type } = &connection.connection_type else {
+            return Err(ConnectorError::ValidationError(format!("{} is not a Cloud Interconnect connection", connection.name)));
+        };
+        
+        // Fail before creating billable resources
+        validate_router_asn(interconnect_type, &connection.bgp_config)?;
+        if matches!(interconnect_type, GcpInterconnectType::Dedicated) && !(2..=4094).contains(&connection.vlan_id) {
+            return Err(ConnectorError::ValidationError(format!("VLAN {} out of range", connection.vlan_id)));
+        }
+        
+        service.update_status(connection_id, ConnectionStatus::Provisioning)?;
+        let result = async {
+            let router = self.ensure_cloud_router(
+                &connection.cloud_region,
+                &options.router,
+                &options.network,
+                connection.bgp_config.cloud_asn,
+            ).await?;
+            self.create_attachment_pair(&connection, &router, options).await
+        }.await;
+        
+        let pair = match result {
+            Ok(pair) => pair,
+            Err(e) => {
+                service.update_status(connection_id, ConnectionStatus::Down)?;
+                return Err(e);
+            }
+        };
+        
+        service.update_status(connection_id, pair.connection_status())?;
+        if let Some(key) = &pair.primary.pairing_key {
+            tracing::info!(
+                "Cloud Interconnect {} awaiting partner; pairing keys {} / {}",
+                connection.name, key, pair.secondary.pairing_key.as_deref().unwrap_or("-")
+            );
+        }
+        self.attachments.insert(*connection_id, pair.clone());
+        
+        Ok(pair)
+    }
+    
+    /// Wait for both attachments, set up BGP on the Cloud Router and take
+    /// the first health reading
+    pub async fn activate_interconnect(
+        &self,
+        service: &CloudConnectorService,
+        connection_id: &Uuid,
+    ) -> Result<Vec<InterconnectBgpSession>, ConnectorError> {
+        let connection = service.get_connection(connection_id)
+            .ok_or(ConnectorError::ConnectionNotFound(*connection_id))?;
+        let mut pair = self.attachments_for(connection_id)?;
+        
+        service.update_status(connection_id, ConnectionStatus::Provisioning)?;
+        for attachment in [&mut pair.primary, &mut pair.secondary] {
+            match self.wait_for_attachment(attachment).await {
+                Ok(current) => *attachment = current,
+                Err(e) => {
+                    service.update_status(connection_id, ConnectionStatus::Down)?;
+                    return Err(e);
+                }
+            }
+        }
+        self.attachments.insert(*connection_id, pair.clone());
+        
+        let sessions = self.configure_bgp(&pair, &connection.bgp_config).await?;
+        
+        // Google assigns the link addresses; our router peers on the primary
+        let primary = &sessions[0];
+        service.update_bgp_addresses(connection_id, primary.peer_ip, primary.cloud_router_ip)?;
+        
+        self.refresh_health(service, connection_id).await?;
+        Ok(sessions)
+    }
+    
+    /// Poll GCP and feed the connection's status and health
+    pub async fn refresh_health(
+        &self,
+        service: &CloudConnectorService,
+        connection_id: &Uuid,
+    ) -> Result<ConnectionHealth, ConnectorError> {
+        let connection = service.get_connection(connection_id)
+            .ok_or(ConnectorError::ConnectionNotFound(*connection_id))?;
+        let mut pair = self.attachments_for(connection_id)?;
+        
+        pair.primary = self.get_attachment(&pair.primary.region, &pair.primary.name).await?;
+        pair.secondary = self.get_attachment(&pair.secondary.region, &pair.secondary.name).await?;
+        self.attachments.insert(*connection_id, pair.clone());
+        
+        // Lifecycle problems override whatever BGP reports
+        let lifecycle = pair.connection_status();
+        if !matches!(lifecycle, ConnectionStatus::Active | ConnectionStatus::Degraded) {
+            service.update_status(connection_id, lifecycle)?;
+            return Ok(connection.health);
+        }
+        
+        let health = self.interconnect_health(&pair).await?;
+        service.update_health(connection_id, health.clone())?;
+        if lifecycle == ConnectionStatus::Degraded {
+            // BGP still up over the surviving attachment; redundancy is gone
+            service.update_status(connection_id, ConnectionStatus::Degraded)?;
+        }
+        Ok(health)
+    }
+    
+    /// Attachment pair tracked for a cloud connection
+    pub fn attachments_for(&self, connection_id: &Uuid) -> Result<AttachmentPair, ConnectorError> {
+        self.attachments.get(connection_id)
+            .map(|p| p.clone())
+            .ok_or_else(|| ConnectorError::ProviderError(format!("No Cloud Interconnect attachments for connection {}", connection_id)))
+    }
+    
+    // -------------------------------------------------------------------------
+    // Compute Engine plumbing
+    // -------------------------------------------------------------------------
+    
+    fn credentials(&self) -> Result<&GcpCredentials, ConnectorError> {
+        self.credentials.as_ref()
+            .ok_or_else(|| ConnectorError::ProviderError("GCP credentials not configured".to_string()))
+    }
+    
+    fn global_url(&self, path: &str) -> Result<String, ConnectorError> {
+        Ok(format!("{}/projects/{}/global/{}", self.compute_endpoint, self.credentials()?.project, path))
+    }
+    
+    fn region_url(&self, region: &str, path: &str) -> Result<String, ConnectorError> {
+        Ok(format!("{}/projects/{}/regions/{}/{}", self.compute_endpoint, self.credentials()?.project, region, path))
+    }
+    
+    /// Configured token, or a metadata-server token cached until shortly
+    /// before expiry
+    async fn access_token(&self) -> Result<String, ConnectorError> {
+        if let Some(token) = &self.credentials()?.access_token {
+            return Ok(token.clone());
+        }
+        if let Some((token, expires_at)) = self.token.lock().clone() {
+            if expires_at > Utc::now() + chrono::Duration::seconds(60) {
+                return Ok(token);
+            }
+        }
+        
+        let response = self.http
+            .get(METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .map_err(|e| ConnectorError::ProviderError(format!("GCP metadata token request failed: {}", e)))?;
+        let body: Value = response.json().await
+            .map_err(|e| ConnectorError::ProviderError(format!("GCP metadata token request failed: {}", e)))?;
+        let Some(token) = body["access_token"].as_str() else {
+            return Err(ConnectorError::ProviderError("GCP metadata server returned no token".to_string()));
+        };
+        
+        let expires_in = body["expires_in"].as_i64().unwrap_or(3600);
+        *self.token.lock() = Some((token.to_string(), Utc::now() + chrono::Duration::seconds(expires_in)));
+        Ok(token.to_string())
+    }
+    
+    async fn request(&self, method: reqwest::Method, url: &str, body: Option<Value>) -> Result<(u16, Value), ConnectorError> {
+        let mut request = self.http.request(method.clone(), url)
+            .bearer_auth(self.access_token().await?);
+        if let Some(body) = &body {
+            request = request.json(body);
+        }
+        
+        let response = request.send().await
+            .map_err(|e| ConnectorError::ProviderError(format!("{} {}: {}", method, url, e)))?;
+        let status = response.status().as_u16();
+        let body = response.bytes().await
+            .map(|b| serde_json::from_slice(&b).unwrap_or(Value::Null))
+            .unwrap_or(Value::Null);
+        Ok((status, body))
+    }
+    
+    async fn send(&self, method: reqwest::Method, url: &str, body: Option<Value>) -> Result<Value, ConnectorError> {
+        let (status, body) = self.request(method.clone(), url, body).await?;
+        if status >= 400 {
+            return Err(ConnectorError::ProviderError(format!(
+                "{} {} returned {}: {}",
+                method, url, status,
+                body["error"]["message"].as_str().unwrap_or(""),
+            )));
+        }
+        Ok(body)
+    }
+    
+    /// GET that maps 404 to `None`
+    async fn get_optional(&self, url: &str) -> Result<Option<Value>, ConnectorError> {
+        match self.request(reqwest::Method::GET, url, None).await? {
+            (404, _) => Ok(None),
+            _ => self.send(reqwest::Method::GET, url, None).await.map(Some),
+        }
+    }
+    
+    /// Poll a Compute operation until it is DONE and surface its errors
+    async fn wait_for_operation(&self, operation: Value) -> Result<Value, ConnectorError> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(3600);
+        let poll = self.poll_interval.min(Duration::from_secs(5));
+        let mut operation = operation;
+        
+        loop {
+            if operation["status"].as_str() == Some("DONE") {
+                if let Some(errors) = operation["error"]["errors"].as_array().filter(|e| !e.is_empty()) {
+                    let messages: Vec<&str> = errors.iter().filter_map(|e| e["message"].as_str()).collect();
+                    return Err(ConnectorError::ProvisioningError(format!(
+                        "GCP operation {} failed: {}", str_field(&operation, "operationType"), messages.join("; ")
+                    )));
+                }
+                return Ok(operation);
+            }
+            
+            let Some(url) = operation["selfLink"].as_str().map(str::to_string) else {
+                // Not an operation (e.g. a synchronous response)
+                return Ok(operation);
+            };
+            if tokio::time::Instant::now() >= deadline {
+                return Err(ConnectorError::ProvisioningError("GCP operation timed out".to_string()));
+            }
+            tokio::time::sleep(poll).await;
+            operation = self.send(reqwest::Method::GET, &url, None).await?;
+        }
     }
     
     /// Generate Terraform configuration
@@ -183,9 +988,9 @@ resource "google_compute_interconnect_attachment" "dedicated" {{
   candidate_subnets        = ["{interconnect_subnet}"]
   edge_availability_domain = "AVAILABILITY_DOMAIN_1"
 }}
-"#, 
-                name = config.name, 
-                project = config.project, 
+"#,
+                name = config.name,
+                project = config.project,
                 region = config.region,
                 location = config.location,
                 link_type = config.link_type,
@@ -193,7 +998,7 @@ resource "google_compute_interconnect_attachment" "dedicated" {{
                 interconnect_subnet = config.interconnect_subnet,
             )
         };
-
+        
         format!(r#"
 # GCP Cloud Interconnect Configuration
 # Generated by OpenSASE Cloud Connector
@@ -301,3 +1106,459 @@ pub struct GcpTerraformConfig {
     pub peer_asn: u32,
     pub peer_ip: String,
 }
+
+/// Partner Interconnect requires Google's ASN on the Cloud Router;
+/// Dedicated uses a private ASN distinct from ours
+fn validate_router_asn(interconnect_type: &GcpInterconnectType, bgp: &BgpConfig) -> Result<(), ConnectorError> {
+    match interconnect_type {
+        GcpInterconnectType::Partner if bgp.cloud_asn != PARTNER_ROUTER_ASN => Err(ConnectorError::BgpError(format!(
+            "Partner Interconnect Cloud Routers must use ASN {}, got {}", PARTNER_ROUTER_ASN, bgp.cloud_asn
+        ))),
+        GcpInterconnectType::Dedicated
+            if !(64512..=65534).contains(&bgp.cloud_asn) && !(4_200_000_000..=4_294_967_294).contains(&bgp.cloud_asn) =>
+        {
+            Err(ConnectorError::BgpError(format!("Cloud Router ASN {} is not a private ASN", bgp.cloud_asn)))
+        }
+        _ if bgp.cloud_asn == bgp.our_asn => Err(ConnectorError::BgpError(format!(
+            "Cloud Router and peer share ASN {}", bgp.our_asn
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn macsec_body(settings: &MacsecSettings) -> Result<Value, ConnectorError> {
+    if settings.keys.is_empty() {
+        return Err(ConnectorError::ValidationError("MACsec needs at least one pre-shared key".to_string()));
+    }
+    let mut names: Vec<&str> = settings.keys.iter().map(|k| k.name.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+    if names.len() != settings.keys.len() {
+        return Err(ConnectorError::ValidationError("MACsec key names must be unique".to_string()));
+    }
+    
+    let keys: Vec<Value> = settings.keys.iter()
+        .map(|key| {
+            let mut value = json!({ "name": key.name });
+            if let Some(start) = key.start_time {
+                value["startTime"] = json!(start.to_rfc3339());
+            }
+            value
+        })
+        .collect();
+    Ok(json!({ "preSharedKeys": keys, "failOpen": settings.fail_open }))
+}
+
+/// Smallest attachment capacity that carries `mbps`
+fn attachment_bandwidth(mbps: u32) -> Result<&'static str, ConnectorError> {
+    ATTACHMENT_BANDWIDTHS.iter()
+        .find(|(capacity, _)| *capacity >= mbps)
+        .map(|(_, name)| *name)
+        .ok_or_else(|| ConnectorError::ValidationError(format!("No VLAN attachment capacity for {} Mbps", mbps)))
+}
+
+fn str_field(value: &Value, field: &str) -> String {
+    value[field].as_str().unwrap_or_default().to_string()
+}
+
+fn strip_prefix(cidr: &str) -> &str {
+    cidr.split('/').next().unwrap_or(cidr)
+}
+
+fn parse_link_ip(cidr: &str) -> Result<IpAddr, ConnectorError> {
+    strip_prefix(cidr).parse()
+        .map_err(|_| ConnectorError::ProviderError(format!("Invalid link address {}", cidr)))
+}
+
+fn parse_interconnect(body: &Value) -> Result<Interconnect, ConnectorError> {
+    let id = body["selfLink"].as_str()
+        .ok_or_else(|| ConnectorError::ProviderError("Interconnect response missing selfLink".to_string()))?;
+    
+    Ok(Interconnect {
+        id: id.to_string(),
+        name: str_field(body, "name"),
+        interconnect_type: match body["interconnectType"].as_str() {
+            Some("PARTNER") => InterconnectType::Partner,
+            _ => InterconnectType::Dedicated,
+        },
+        link_type: str_field(body, "linkType"),
+        location: str_field(body, "location"),
+        state: str_field(body, "state"),
+        operational_status: str_field(body, "operationalStatus"),
+        requested_link_count: body["requestedLinkCount"].as_u64().unwrap_or(0) as u32,
+        provisioned_link_count: body["provisionedLinkCount"].as_u64().unwrap_or(0) as u32,
+        available_features: body["availableFeatures"].as_array().into_iter().flatten()
+            .filter_map(|f| f.as_str().map(str::to_string))
+            .collect(),
+        macsec_enabled: body["macsecEnabled"].as_bool().unwrap_or(false),
+    })
+}
+
+fn parse_attachment(region: &str, body: &Value) -> Result<InterconnectAttachment, ConnectorError> {
+    let id = body["selfLink"].as_str()
+        .ok_or_else(|| ConnectorError::ProviderError("Attachment response missing selfLink".to_string()))?;
+    let optional = |field: &str| body[field].as_str().filter(|s| !s.is_empty()).map(str::to_string);
+    
+    Ok(InterconnectAttachment {
+        id: id.to_string(),
+        name: str_field(body, "name"),
+        router: str_field(body, "router"),
+        region: region.to_string(),
+        attachment_type: match body["type"].as_str() {
+            Some("PARTNER") => GcpInterconnectType::Partner,
+            _ => GcpInterconnectType::Dedicated,
+        },
+        vlan_tag: body["vlanTag8021q"].as_u64().unwrap_or(0) as u16,
+        pairing_key: optional("pairingKey"),
+        edge_availability_domain: str_field(body, "edgeAvailabilityDomain"),
+        state: str_field(body, "state"),
+        operational_status: str_field(body, "operationalStatus"),
+        cloud_router_ip: optional("cloudRouterIpAddress"),
+        customer_router_ip: optional("customerRouterIpAddress"),
+        partner_name: body["partnerMetadata"]["partnerName"].as_str().map(str::to_string),
+    })
+}
+
+fn parse_bgp_state(state: &str) -> BgpState {
+    match state.to_ascii_lowercase().as_str() {
+        "connect" => BgpState::Connect,
+        "active" => BgpState::Active,
+        "opensent" => BgpState::OpenSent,
+        "openconfirm" => BgpState::OpenConfirm,
+        "established" => BgpState::Established,
+        _ => BgpState::Idle,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Compute Engine stand-in answering `(method, path suffix)` routes with
+    /// JSON; records each request line and body it served
+    async fn compute(routes: Vec<(&'static str, &'static str, Value)>) -> (String, Arc<parking_lot::Mutex<Vec<(String, Value)>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let log = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let seen = log.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head.lines()
+                            .filter_map(|l| l.split_once(':'))
+                            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if body.len() >= length || n == 0 {
+                            break (head.to_string(), body.to_string());
+                        }
+                    } else if n == 0 {
+                        break (text, String::new());
+                    }
+                };
+                let mut line = head.lines().next().unwrap_or_default().split(' ');
+                let (method, path) = (line.next().unwrap_or_default(), line.next().unwrap_or_default());
+                seen.lock().push((format!("{} {}", method, path), serde_json::from_str(&body).unwrap_or(Value::Null)));
+
+                let (status, body) = match routes.iter().find(|(m, p, _)| *m == method && path.ends_with(p)) {
+                    Some((_, _, body)) => ("200 OK", body.to_string()),
+                    None => ("404 Not Found", json!({ "error": { "message": path } }).to_string()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (base, log)
+    }
+
+    fn manager(base: &str) -> GcpConnectorManager {
+        GcpConnectorManager::new()
+            .with_credentials(GcpCredentials { project: "proj".to_string(), access_token: Some("token".to_string()) })
+            .with_endpoint(base)
+            .with_poll_interval(Duration::from_millis(10))
+    }
+
+    fn bgp(our_asn: u32, cloud_asn: u32) -> BgpConfig {
+        BgpConfig {
+            our_asn,
+            cloud_asn,
+            our_ip: "169.254.10.2".parse().unwrap(),
+            cloud_ip: "169.254.10.1".parse().unwrap(),
+            md5_auth: Some("s3cret".to_string()),
+            advertised_prefixes: vec!["10.10.0.0/16".parse().unwrap()],
+            received_prefixes: vec![],
+            local_preference: 100,
+            med: 50,
+        }
+    }
+
+    fn attachment(name: &str, state: &str, operational: &str) -> InterconnectAttachment {
+        InterconnectAttachment {
+            id: format!("https://compute.example/projects/proj/regions/us-east4/interconnectAttachments/{}", name),
+            name: name.to_string(),
+            router: "edge".to_string(),
+            region: "us-east4".to_string(),
+            attachment_type: GcpInterconnectType::Partner,
+            vlan_tag: 0,
+            pairing_key: None,
+            edge_availability_domain: "AVAILABILITY_DOMAIN_1".to_string(),
+            state: state.to_string(),
+            operational_status: operational.to_string(),
+            cloud_router_ip: Some("169.254.10.1/29".to_string()),
+            customer_router_ip: Some("169.254.10.2/29".to_string()),
+            partner_name: None,
+        }
+    }
+
+    fn interconnect(interconnect_type: InterconnectType, features: &[&str]) -> Interconnect {
+        Interconnect {
+            id: "https://compute.example/projects/proj/global/interconnects/ic-1".to_string(),
+            name: "ic-1".to_string(),
+            interconnect_type,
+            link_type: "LINK_TYPE_ETHERNET_10G_LR".to_string(),
+            location: "iad-zone1-1".to_string(),
+            state: "ACTIVE".to_string(),
+            operational_status: "OS_ACTIVE".to_string(),
+            requested_link_count: 1,
+            provisioned_link_count: 1,
+            available_features: features.iter().map(|f| f.to_string()).collect(),
+            macsec_enabled: false,
+        }
+    }
+
+    #[test]
+    fn test_attachment_and_pair_status() {
+        for (state, operational, expected) in [
+            ("ACTIVE", "OS_ACTIVE", ConnectionStatus::Active),
+            ("ACTIVE", "OS_UNPROVISIONED", ConnectionStatus::Provisioning),
+            ("PENDING_CUSTOMER", "", ConnectionStatus::Provisioning),
+            ("PENDING_PARTNER", "", ConnectionStatus::Pending),
+            ("PARTNER_REQUEST_RECEIVED", "", ConnectionStatus::Pending),
+            ("DEFUNCT", "", ConnectionStatus::Down),
+            ("UNPROVISIONED", "", ConnectionStatus::Down),
+        ] {
+            let attachment = attachment("a", state, operational);
+            assert_eq!(attachment.connection_status(), expected, "{} {}", state, operational);
+            assert_eq!(attachment.awaiting_activation(), state == "PENDING_CUSTOMER");
+        }
+
+        let pair = |primary: (&str, &str), secondary: (&str, &str)| AttachmentPair {
+            router: "edge".to_string(),
+            primary: attachment("p", primary.0, primary.1),
+            secondary: attachment("s", secondary.0, secondary.1),
+        };
+        let up = ("ACTIVE", "OS_ACTIVE");
+        assert_eq!(pair(up, up).connection_status(), ConnectionStatus::Active);
+        assert_eq!(pair(up, ("DEFUNCT", "")).connection_status(), ConnectionStatus::Degraded);
+        assert_eq!(pair(("PENDING_PARTNER", ""), up).connection_status(), ConnectionStatus::Degraded);
+        assert_eq!(pair(("PENDING_PARTNER", ""), ("DEFUNCT", "")).connection_status(), ConnectionStatus::Down);
+        assert_eq!(pair(("PENDING_CUSTOMER", ""), ("PENDING_PARTNER", "")).connection_status(), ConnectionStatus::Pending);
+        assert_eq!(pair(("PENDING_CUSTOMER", ""), ("ACTIVE", "")).connection_status(), ConnectionStatus::Provisioning);
+    }
+
+    #[test]
+    fn test_router_asn_bandwidth_and_domains() {
+        assert!(validate_router_asn(&GcpInterconnectType::Partner, &bgp(65010, PARTNER_ROUTER_ASN)).is_ok());
+        assert!(validate_router_asn(&GcpInterconnectType::Partner, &bgp(65010, 64512)).is_err());
+        assert!(validate_router_asn(&GcpInterconnectType::Dedicated, &bgp(65010, 64512)).is_ok());
+        assert!(validate_router_asn(&GcpInterconnectType::Dedicated, &bgp(65010, 4_200_000_001)).is_ok());
+        assert!(validate_router_asn(&GcpInterconnectType::Dedicated, &bgp(65010, PARTNER_ROUTER_ASN)).is_err());
+        assert!(validate_router_asn(&GcpInterconnectType::Dedicated, &bgp(65010, 65010)).is_err());
+
+        assert_eq!(attachment_bandwidth(50).unwrap(), "BPS_50M");
+        assert_eq!(attachment_bandwidth(1_000).unwrap(), "BPS_1G");
+        assert_eq!(attachment_bandwidth(1_001).unwrap(), "BPS_2G");
+        assert!(attachment_bandwidth(200_000).is_err());
+
+        assert_eq!(EdgeAvailabilityDomain::of_location("iad-zone1-1"), Some(EdgeAvailabilityDomain::Domain1));
+        assert_eq!(
+            EdgeAvailabilityDomain::of_location("https://compute.example/projects/p/global/interconnectLocations/lhr-zone2-47"),
+            Some(EdgeAvailabilityDomain::Domain2)
+        );
+        assert_eq!(EdgeAvailabilityDomain::of_location("ams-3"), None);
+    }
+
+    #[test]
+    fn test_macsec_body() {
+        let key = |name: &str, start: Option<DateTime<Utc>>| MacsecKey { name: name.to_string(), start_time: start };
+        let start = Utc::now();
+
+        let body = macsec_body(&MacsecSettings { keys: vec![key("k1", None), key("k2", Some(start))], fail_open: true }).unwrap();
+        assert_eq!(body["failOpen"], json!(true));
+        assert_eq!(body["preSharedKeys"][0], json!({ "name": "k1" }));
+        assert_eq!(body["preSharedKeys"][1]["startTime"], json!(start.to_rfc3339()));
+
+        assert!(macsec_body(&MacsecSettings { keys: vec![], fail_open: false }).is_err());
+        assert!(macsec_body(&MacsecSettings { keys: vec![key("k1", None), key("k1", Some(start))], fail_open: false }).is_err());
+    }
+
+    #[test]
+    fn test_parse_interconnect_and_attachment() {
+        let interconnect = parse_interconnect(&json!({
+            "selfLink": "https://compute.example/projects/proj/global/interconnects/ic-1",
+            "name": "ic-1",
+            "interconnectType": "DEDICATED",
+            "location": "https://compute.example/projects/proj/global/interconnectLocations/iad-zone1-1",
+            "state": "ACTIVE",
+            "operationalStatus": "OS_ACTIVE",
+            "requestedLinkCount": 2,
+            "provisionedLinkCount": 2,
+            "availableFeatures": ["IF_MACSEC"],
+            "macsecEnabled": true
+        })).unwrap();
+        assert!(interconnect.is_active() && interconnect.supports_macsec() && interconnect.macsec_enabled);
+        assert_eq!((interconnect.requested_link_count, interconnect.provisioned_link_count), (2, 2));
+        assert!(parse_interconnect(&json!({ "name": "ic-1" })).is_err());
+
+        let attachment = parse_attachment("us-east4", &json!({
+            "selfLink": "https://compute.example/projects/proj/regions/us-east4/interconnectAttachments/a",
+            "name": "a",
+            "type": "PARTNER",
+            "vlanTag8021q": 1234,
+            "pairingKey": "7e51371e-72a3-40b5-b844-2e3efefaee59/us-east4/1",
+            "customerRouterIpAddress": "",
+            "state": "PENDING_CUSTOMER",
+            "partnerMetadata": { "partnerName": "Equinix" }
+        })).unwrap();
+        assert!(matches!(attachment.attachment_type, GcpInterconnectType::Partner));
+        assert_eq!((attachment.vlan_tag, attachment.region.as_str()), (1234, "us-east4"));
+        assert!(attachment.pairing_key.is_some() && attachment.customer_router_ip.is_none());
+        assert_eq!(attachment.partner_name.as_deref(), Some("Equinix"));
+        assert!(attachment.awaiting_activation());
+
+        assert_eq!(parse_bgp_state("ESTABLISHED"), BgpState::Established);
+        assert_eq!(parse_bgp_state("OpenConfirm"), BgpState::OpenConfirm);
+        assert_eq!(parse_bgp_state(""), BgpState::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_configure_bgp_reuses_partner_interfaces() {
+        let router_path = "/projects/proj/regions/us-east4/routers/edge";
+        let primary = attachment("opensase-primary", "ACTIVE", "OS_ACTIVE");
+        let mut secondary = attachment("opensase-secondary", "ACTIVE", "OS_ACTIVE");
+        secondary.cloud_router_ip = Some("169.254.20.1/29".to_string());
+        secondary.customer_router_ip = Some("169.254.20.2/29".to_string());
+
+        let (base, log) = compute(vec![
+            ("GET", router_path, json!({
+                "fingerprint": "abc=",
+                "interfaces": [
+                    { "name": "partner-if-0", "linkedInterconnectAttachment": primary.id }
+                ],
+                "bgpPeers": [
+                    { "name": "partner-peer-0", "interfaceName": "partner-if-0" }
+                ]
+            })),
+            ("PATCH", router_path, json!({ "status": "DONE" })),
+        ]).await;
+        let pair = AttachmentPair { router: format!("{}{}", base, router_path), primary, secondary };
+
+        let sessions = manager(&base).configure_bgp(&pair, &bgp(65010, PARTNER_ROUTER_ASN)).await.unwrap();
+        assert_eq!(sessions[0].interface_name, "partner-if-0");
+        assert_eq!(sessions[1].interface_name, "opensase-secondary-if");
+        assert_eq!(sessions[1].cloud_router_ip, "169.254.20.1".parse::<IpAddr>().unwrap());
+        assert_eq!(sessions[1].peer_ip, "169.254.20.2".parse::<IpAddr>().unwrap());
+
+        let log = log.lock().clone();
+        let (_, patch) = log.iter().find(|(line, _)| line.starts_with("PATCH")).unwrap();
+        assert_eq!(patch["fingerprint"], json!("abc="));
+        assert_eq!(patch["interfaces"].as_array().unwrap().len(), 2);
+        assert_eq!(patch["interfaces"][1]["ipRange"], json!("169.254.20.1/29"));
+
+        // The partner's peer on the reused interface is replaced by ours
+        let peers = patch["bgpPeers"].as_array().unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0]["name"], json!("opensase-primary-peer"));
+        assert_eq!(peers[0]["peerIpAddress"], json!("169.254.10.2"));
+        assert_eq!((peers[0]["peerAsn"].clone(), peers[0]["advertisedRoutePriority"].clone()), (json!(65010), json!(50)));
+        assert_eq!(peers[1]["md5AuthenticationKeyName"], json!("opensase-secondary-md5"));
+        assert_eq!(patch["md5AuthenticationKeys"].as_array().unwrap().len(), 2);
+
+        // Attachments without link addresses cannot peer
+        let mut pending = pair.clone();
+        pending.secondary.customer_router_ip = None;
+        assert!(matches!(
+            manager(&base).configure_bgp(&pending, &bgp(65010, PARTNER_ROUTER_ASN)).await,
+            Err(ConnectorError::ProvisioningError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_interconnect_health_from_router_status() {
+        let router_path = "/projects/proj/regions/us-east4/routers/edge";
+        let status = |primary: Value, secondary: Value| json!({ "result": { "bgpPeerStatus": [
+            { "name": "someone-else-peer", "status": "UP", "uptimeSeconds": "999999", "numLearnedRoutes": 90 },
+            primary,
+            secondary
+        ]}});
+
+        let (base, _) = compute(vec![("GET", "/routers/edge/getRouterStatus", status(
+            json!({ "name": "p-peer", "status": "UP", "uptimeSeconds": "600", "numLearnedRoutes": 3, "advertisedRoutes": [{}, {}] }),
+            json!({ "name": "s-peer", "status": "UP", "uptimeSeconds": "7200", "numLearnedRoutes": 2, "advertisedRoutes": [{}] }),
+        ))]).await;
+        let pair = AttachmentPair {
+            router: format!("{}{}", base, router_path),
+            primary: attachment("p", "ACTIVE", "OS_ACTIVE"),
+            secondary: attachment("s", "ACTIVE", "OS_ACTIVE"),
+        };
+        let health = manager(&base).interconnect_health(&pair).await.unwrap();
+        assert_eq!((health.bgp_state, health.bgp_uptime_secs), (BgpState::Established, 7200));
+        assert_eq!((health.prefixes_advertised, health.prefixes_received), (3, 2));
+
+        let (base, _) = compute(vec![("GET", "/routers/edge/getRouterStatus", status(
+            json!({ "name": "p-peer", "status": "DOWN", "state": "Active" }),
+            json!({ "name": "s-peer", "status": "DOWN", "state": "Idle" }),
+        ))]).await;
+        let pair = AttachmentPair { router: format!("{}{}", base, router_path), ..pair };
+        let health = manager(&base).interconnect_health(&pair).await.unwrap();
+        assert_eq!((health.bgp_state, health.bgp_uptime_secs, health.prefixes_advertised), (BgpState::Active, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_macsec_requires_capable_dedicated_ports() {
+        let (base, log) = compute(vec![
+            ("PATCH", "/global/interconnects/ic-1", json!({
+                "status": "DONE",
+                "operationType": "patch",
+                "error": { "errors": [{ "message": "Key slot limit exceeded" }] }
+            })),
+        ]).await;
+        let manager = manager(&base);
+        let settings = MacsecSettings {
+            keys: vec![MacsecKey { name: "k1".to_string(), start_time: None }],
+            fail_open: false,
+        };
+
+        assert!(matches!(
+            manager.configure_macsec(&interconnect(InterconnectType::Partner, &["IF_MACSEC"]), &settings).await,
+            Err(ConnectorError::ValidationError(_))
+        ));
+        assert!(matches!(
+            manager.configure_macsec(&interconnect(InterconnectType::Dedicated, &[]), &settings).await,
+            Err(ConnectorError::ProvisioningError(_))
+        ));
+        assert!(log.lock().is_empty());
+
+        let mut capable = interconnect(InterconnectType::Dedicated, &["IF_MACSEC"]);
+        capable.id = format!("{}/projects/proj/global/interconnects/ic-1", base);
+        match manager.configure_macsec(&capable, &settings).await {
+            Err(ConnectorError::ProvisioningError(message)) => assert!(message.contains("Key slot limit exceeded")),
+            other => panic!("expected operation failure, got {:?}", other.map(|i| i.name)),
+        }
+        let log = log.lock().clone();
+        assert_eq!(log[0].1["macsecEnabled"], json!(true));
+        assert_eq!(log[0].1["macsec"]["preSharedKeys"][0]["name"], json!("k1"));
+    }
+}
@@ -51,8 +51,12 @@ use uuid::Uuid;
 
 pub mod aws;
 pub mod azure;
+pub mod cost;
 pub mod gcp;
+pub mod monitor;
+pub mod reconcile;
 pub mod routing;
+pub mod vpn;
 
 // =============================================================================
 // Core Types
@@ -215,6 +219,7 @@ pub enum BgpState {
 pub struct CloudConnectorService {
     connections: dashmap::DashMap<Uuid, CloudConnection>,
     route_manager: routing::CloudRouteManager,
+    cost_tracker: cost::CostTracker,
 }
 
 impl CloudConnectorService {
@@ -223,6 +228,7 @@ impl CloudConnectorService {
         Self {
             connections: dashmap::DashMap::new(),
             route_manager: routing::CloudRouteManager::new(),
+            cost_tracker: cost::CostTracker::default(),
         }
     }
     
@@ -264,6 +270,11 @@ impl CloudConnectorService {
         self.connections.get(id).map(|r| r.clone())
     }
     
+    /// List connections across all tenants
+    pub fn all_connections(&self) -> Vec<CloudConnection> {
+        self.connections.iter().map(|r| r.clone()).collect()
+    }
+    
     /// List connections for tenant
     pub fn list_connections(&self, tenant_id: &Uuid) -> Vec<CloudConnection> {
         self.connections
@@ -290,6 +301,7 @@ impl CloudConnectorService {
         // Check state before moving health
         let is_established = health.bgp_state == BgpState::Established;
         let current_status = conn.status;
+        self.cost_tracker.record(*id, &health);
         
         conn.health = health;
         conn.updated_at = Utc::now();
@@ -306,11 +318,43 @@ impl CloudConnectorService {
         Ok(())
     }
     
+    /// Record BGP peer addresses assigned by the cloud provider
+    pub fn update_bgp_addresses(&self, id: &Uuid, our_ip: IpAddr, cloud_ip: IpAddr) -> Result<(), ConnectorError> {
+        let mut conn = self.connections.get_mut(id)
+            .ok_or(ConnectorError::ConnectionNotFound(*id))?;
+        conn.bgp_config.our_ip = our_ip;
+        conn.bgp_config.cloud_ip = cloud_ip;
+        conn.updated_at = Utc::now();
+        Ok(())
+    }
+    
+    /// Update a connection's in-place settings (name, bandwidth, BGP policy)
+    pub async fn update_connection(
+        &self,
+        id: &Uuid,
+        request: CreateConnectionRequest,
+    ) -> Result<CloudConnection, ConnectorError> {
+        self.validate_request(&request)?;
+        
+        let mut conn = self.connections.get_mut(id)
+            .ok_or(ConnectorError::ConnectionNotFound(*id))?;
+        conn.name = request.name;
+        conn.bandwidth_mbps = request.bandwidth_mbps;
+        
+        // Keep what BGP has learned; only the configured policy changes
+        let received = std::mem::take(&mut conn.bgp_config.received_prefixes);
+        conn.bgp_config = BgpConfig { received_prefixes: received, ..request.bgp_config };
+        conn.updated_at = Utc::now();
+        
+        Ok(conn.clone())
+    }
+    
     /// Delete connection
     pub async fn delete_connection(&self, id: &Uuid) -> Result<(), ConnectorError> {
         self.update_status(id, ConnectionStatus::Deleting)?;
         // In production: deprovision cloud resources
         self.connections.remove(id);
+        self.cost_tracker.remove(id);
         Ok(())
     }
     
@@ -344,11 +388,34 @@ impl CloudConnectorService {
         Ok(())
     }
     
+    /// Balance traffic across several connections to the same provider/region
+    pub async fn configure_load_balancing(
+        &self,
+        connection_ids: &[Uuid],
+        balancing: routing::LoadBalancing,
+    ) -> Result<(), ConnectorError> {
+        let connections = connection_ids.iter()
+            .map(|id| self.get_connection(id).ok_or(ConnectorError::ConnectionNotFound(*id)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.route_manager.configure_multipath(&connections, &balancing)
+    }
+    
+    /// Route manager backing this service
+    pub fn route_manager(&self) -> &routing::CloudRouteManager {
+        &self.route_manager
+    }
+    
     /// Handle failover when connection goes down
     pub async fn handle_failover(&self, failed_id: &Uuid) -> Result<(), ConnectorError> {
         let failed = self.get_connection(failed_id)
             .ok_or(ConnectorError::ConnectionNotFound(*failed_id))?;
         
+        // Remaining multipath members take over without promoting a backup
+        if self.route_manager.withdraw_connection(failed_id) {
+            tracing::info!("Withdrew {} ({}); remaining paths carry its traffic", failed.name, failed.cloud_provider.as_str());
+            return Ok(());
+        }
+        
         // Find backup connections for same tenant
         let backups: Vec<_> = self.connections
             .iter()
@@ -371,13 +438,50 @@ impl CloudConnectorService {
         Ok(())
     }
     
+    /// Egress cost tracker and price book
+    pub fn cost_tracker(&self) -> &cost::CostTracker {
+        &self.cost_tracker
+    }
+    
+    /// Monthly egress cost projections for a tenant's connections
+    pub fn cost_projections(&self, tenant_id: &Uuid) -> Vec<cost::CostProjection> {
+        let now = Utc::now();
+        let mut projections: Vec<_> = self.list_connections(tenant_id)
+            .iter()
+            .map(|c| self.cost_tracker.project(c, now))
+            .collect();
+        projections.sort_by(|a, b| b.projected_usd.total_cmp(&a.projected_usd));
+        projections
+    }
+    
+    /// Steer a tenant's bulk traffic over its cheapest viable connection
+    ///
+    /// Latency-sensitive traffic keeps its normal routes. Returns the chosen
+    /// connection, or `None` (steering cleared) when nothing qualifies.
+    pub fn apply_cost_steering(
+        &self,
+        tenant_id: &Uuid,
+        policy: &cost::CostSteeringPolicy,
+    ) -> Result<Option<Uuid>, ConnectorError> {
+        let connections = self.list_connections(tenant_id);
+        let chosen = self.cost_tracker.cheapest_viable(&connections, policy);
+        
+        match chosen {
+            Some(connection) => tracing::info!(
+                "Steering bulk traffic for {} over {} ({} {})",
+                tenant_id, connection.name, connection.cloud_provider.as_str(), connection.cloud_region
+            ),
+            None => tracing::warn!("No connection meets the cost steering policy for {}", tenant_id),
+        }
+        
+        let chosen = chosen.map(|c| c.id);
+        self.route_manager.set_bulk_preference(chosen);
+        Ok(chosen)
+    }
+    
     /// Generate BIRD BGP configuration
     pub fn generate_bird_config(&self, connection: &CloudConnection) -> String {
-        let session_name = format!(
-            "cloud_{}_{}",
-            connection.cloud_provider.as_str().to_lowercase(),
-            connection.id.to_string().replace('-', "_")[..8].to_string()
-        );
+        let session_name = bird_session_name(connection);
         
         let advertised = connection.bgp_config.advertised_prefixes
             .iter()
@@ -435,6 +539,15 @@ protocol bgp {session_name} {{
     }
 }
 
+/// BIRD protocol name for a connection's BGP session
+pub fn bird_session_name(connection: &CloudConnection) -> String {
+    format!(
+        "cloud_{}_{}",
+        connection.cloud_provider.as_str().to_lowercase(),
+        &connection.id.to_string().replace('-', "_")[..8]
+    )
+}
+
 impl Default for CloudConnectorService {
     fn default() -> Self {
         Self::new()
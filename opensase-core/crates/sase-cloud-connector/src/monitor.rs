@@ -0,0 +1,685 @@
+//! Cloud connection health monitor
+//!
+//! Polls the PoP's BGP daemon (BIRD or GoBGP) for per-session state and
+//! prefix counts, probes latency, jitter and loss across each connection,
+//! and feeds `update_health`. Connections that breach thresholds for
+//! several consecutive polls are failed over.
+
+use crate::{BgpState, CloudConnection, CloudConnectorService, ConnectionHealth, ConnectionStatus, ConnectorError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// One BGP session as reported by the routing daemon
+#[derive(Clone, Debug)]
+pub struct BgpSessionStats {
+    /// Protocol name (BIRD) or neighbor description
+    pub name: String,
+    pub neighbor: Option<IpAddr>,
+    pub state: BgpState,
+    pub prefixes_received: u32,
+    pub prefixes_advertised: u32,
+}
+
+/// Source of BGP session state
+#[async_trait]
+pub trait BgpSessionSource: Send + Sync {
+    async fn sessions(&self) -> Result<Vec<BgpSessionStats>, ConnectorError>;
+}
+
+/// Result of probing across a connection
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProbeResult {
+    pub latency_ms: f64,
+    pub jitter_ms: f64,
+    /// Fraction of probes lost, 0.0 - 1.0
+    pub packet_loss: f64,
+}
+
+/// Latency/jitter/loss prober
+#[async_trait]
+pub trait Prober: Send + Sync {
+    async fn probe(&self, target: IpAddr) -> ProbeResult;
+}
+
+/// Thresholds that mark a connection unhealthy
+#[derive(Clone, Debug)]
+pub struct HealthThresholds {
+    pub max_latency_ms: f64,
+    pub max_jitter_ms: f64,
+    pub max_packet_loss: f64,
+    /// Session drops tolerated within `flap_window`
+    pub max_flaps: u32,
+    pub flap_window: Duration,
+    /// Consecutive unhealthy polls before failing over
+    pub failover_after: u32,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_latency_ms: 100.0,
+            max_jitter_ms: 30.0,
+            max_packet_loss: 0.05,
+            max_flaps: 3,
+            flap_window: Duration::from_secs(600),
+            failover_after: 3,
+        }
+    }
+}
+
+/// Why a connection was judged unhealthy
+#[derive(Clone, Debug, PartialEq)]
+pub enum HealthBreach {
+    SessionDown(BgpState),
+    SessionMissing,
+    Latency(f64),
+    Jitter(f64),
+    PacketLoss(f64),
+    Flapping(u32),
+}
+
+/// Outcome of one poll for one connection
+#[derive(Clone, Debug)]
+pub struct HealthVerdict {
+    pub connection_id: Uuid,
+    pub health: ConnectionHealth,
+    pub flaps: u32,
+    pub breaches: Vec<HealthBreach>,
+    pub consecutive_unhealthy: u32,
+    pub failed_over: bool,
+}
+
+#[derive(Default)]
+struct SessionTracker {
+    last_state: Option<BgpState>,
+    established_since: Option<DateTime<Utc>>,
+    flaps: VecDeque<DateTime<Utc>>,
+    consecutive_unhealthy: u32,
+    failed_over: bool,
+}
+
+/// Background health monitor for cloud connections
+pub struct HealthMonitor {
+    service: Arc<CloudConnectorService>,
+    sessions: Arc<dyn BgpSessionSource>,
+    prober: Arc<dyn Prober>,
+    thresholds: HealthThresholds,
+    interval: Duration,
+    trackers: dashmap::DashMap<Uuid, SessionTracker>,
+    vpn_fallback: Option<Arc<crate::vpn::VpnFallbackManager>>,
+}
+
+impl HealthMonitor {
+    pub fn new(
+        service: Arc<CloudConnectorService>,
+        sessions: Arc<dyn BgpSessionSource>,
+        prober: Arc<dyn Prober>,
+    ) -> Self {
+        Self {
+            service,
+            sessions,
+            prober,
+            thresholds: HealthThresholds::default(),
+            interval: Duration::from_secs(30),
+            trackers: dashmap::DashMap::new(),
+            vpn_fallback: None,
+        }
+    }
+    
+    pub fn with_thresholds(mut self, thresholds: HealthThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+    
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    
+    /// Bring VPN fallbacks up/down after every poll
+    pub fn with_vpn_fallback(mut self, fallback: Arc<crate::vpn::VpnFallbackManager>) -> Self {
+        self.vpn_fallback = Some(fallback);
+        self
+    }
+    
+    /// Run the monitor until the task is aborted
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.poll_once().await {
+                    tracing::warn!("Cloud connection health poll failed: {}", e);
+                }
+            }
+        })
+    }
+    
+    /// Poll every monitored connection once
+    pub async fn poll_once(&self) -> Result<Vec<HealthVerdict>, ConnectorError> {
+        let sessions = self.sessions.sessions().await?;
+        let connections: Vec<_> = self.service.all_connections()
+            .into_iter()
+            .filter(|c| matches!(c.status, ConnectionStatus::Active | ConnectionStatus::Degraded | ConnectionStatus::Down))
+            .collect();
+        
+        // Forget connections that were deleted
+        self.trackers.retain(|id, _| connections.iter().any(|c| c.id == *id));
+        
+        // Probe all connections concurrently
+        let handles: Vec<_> = connections.iter()
+            .map(|c| {
+                let prober = self.prober.clone();
+                let target = c.bgp_config.cloud_ip;
+                tokio::spawn(async move { prober.probe(target).await })
+            })
+            .collect();
+        let mut probes = Vec::with_capacity(handles.len());
+        for handle in handles {
+            probes.push(handle.await.unwrap_or(ProbeResult { packet_loss: 1.0, ..Default::default() }));
+        }
+        
+        let mut verdicts = Vec::with_capacity(connections.len());
+        for (connection, probe) in connections.iter().zip(probes) {
+            let session = find_session(&sessions, connection);
+            let verdict = self.evaluate(connection, session, probe);
+            
+            self.service.update_health(&connection.id, verdict.health.clone())?;
+            // update_health trusts BGP alone; threshold breaches still degrade
+            if !verdict.breaches.is_empty() && verdict.health.bgp_state == BgpState::Established {
+                self.service.update_status(&connection.id, ConnectionStatus::Degraded)?;
+            }
+            
+            if verdict.failed_over {
+                tracing::warn!(
+                    "Connection {} unhealthy for {} polls ({:?}), failing over",
+                    connection.name, verdict.consecutive_unhealthy, verdict.breaches
+                );
+                self.service.handle_failover(&connection.id).await?;
+            }
+            verdicts.push(verdict);
+        }
+        
+        if let Some(fallback) = &self.vpn_fallback {
+            for action in fallback.reconcile(&self.service).await? {
+                tracing::info!("VPN fallback: {:?}", action);
+            }
+        }
+        
+        Ok(verdicts)
+    }
+    
+    fn evaluate(&self, connection: &CloudConnection, session: Option<&BgpSessionStats>, probe: ProbeResult) -> HealthVerdict {
+        let now = Utc::now();
+        let state = session.map(|s| s.state).unwrap_or(BgpState::Idle);
+        let mut tracker = self.trackers.entry(connection.id).or_default();
+        
+        if state == BgpState::Established {
+            tracker.established_since.get_or_insert(now);
+        } else {
+            if tracker.last_state == Some(BgpState::Established) {
+                tracker.flaps.push_back(now);
+            }
+            tracker.established_since = None;
+        }
+        tracker.last_state = Some(state);
+        
+        let window = chrono::Duration::from_std(self.thresholds.flap_window).unwrap_or_else(|_| chrono::Duration::zero());
+        while tracker.flaps.front().is_some_and(|t| *t < now - window) {
+            tracker.flaps.pop_front();
+        }
+        let flaps = tracker.flaps.len() as u32;
+        
+        let previous = &connection.health;
+        let health = ConnectionHealth {
+            bgp_state: state,
+            bgp_uptime_secs: tracker.established_since
+                .map(|since| (now - since).num_seconds().max(0) as u64)
+                .unwrap_or(0),
+            prefixes_received: session.map(|s| s.prefixes_received).unwrap_or(0),
+            prefixes_advertised: session.map(|s| s.prefixes_advertised).unwrap_or(0),
+            latency_ms: probe.latency_ms,
+            jitter_ms: probe.jitter_ms,
+            packet_loss: probe.packet_loss,
+            last_checked: now,
+            ..previous.clone()
+        };
+        
+        let mut breaches = Vec::new();
+        match session {
+            None => breaches.push(HealthBreach::SessionMissing),
+            Some(_) if state != BgpState::Established => breaches.push(HealthBreach::SessionDown(state)),
+            _ => {}
+        }
+        if probe.packet_loss > self.thresholds.max_packet_loss {
+            breaches.push(HealthBreach::PacketLoss(probe.packet_loss));
+        }
+        // Latency and jitter are meaningless once every probe is lost
+        if probe.packet_loss < 1.0 {
+            if probe.latency_ms > self.thresholds.max_latency_ms {
+                breaches.push(HealthBreach::Latency(probe.latency_ms));
+            }
+            if probe.jitter_ms > self.thresholds.max_jitter_ms {
+                breaches.push(HealthBreach::Jitter(probe.jitter_ms));
+            }
+        }
+        if flaps > self.thresholds.max_flaps {
+            breaches.push(HealthBreach::Flapping(flaps));
+        }
+        
+        let mut failed_over = false;
+        if breaches.is_empty() {
+            tracker.consecutive_unhealthy = 0;
+            tracker.failed_over = false;
+        } else {
+            tracker.consecutive_unhealthy += 1;
+            // Fail over once per outage, not on every poll
+            if tracker.consecutive_unhealthy >= self.thresholds.failover_after && !tracker.failed_over {
+                tracker.failed_over = true;
+                failed_over = true;
+            }
+        }
+        
+        HealthVerdict {
+            connection_id: connection.id,
+            health,
+            flaps,
+            breaches,
+            consecutive_unhealthy: tracker.consecutive_unhealthy,
+            failed_over,
+        }
+    }
+}
+
+/// Match a connection to its session by BIRD protocol name, then neighbor
+fn find_session<'a>(sessions: &'a [BgpSessionStats], connection: &CloudConnection) -> Option<&'a BgpSessionStats> {
+    let name = crate::bird_session_name(connection);
+    sessions.iter()
+        .find(|s| s.name == name)
+        .or_else(|| sessions.iter().find(|s| s.neighbor == Some(connection.bgp_config.cloud_ip)))
+}
+
+// =============================================================================
+// BIRD
+// =============================================================================
+
+/// Reads sessions from the BIRD control socket
+pub struct BirdSessionSource {
+    socket_path: String,
+}
+
+impl BirdSessionSource {
+    pub fn new(socket_path: impl Into<String>) -> Self {
+        Self { socket_path: socket_path.into() }
+    }
+    
+    async fn command(&self, command: &str) -> Result<String, ConnectorError> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        
+        let stream = tokio::net::UnixStream::connect(&self.socket_path).await
+            .map_err(|e| ConnectorError::BgpError(format!("BIRD socket {}: {}", self.socket_path, e)))?;
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        
+        // Greeting: "0001 BIRD x.y ready."
+        lines.next_line().await.map_err(|e| ConnectorError::BgpError(e.to_string()))?;
+        write.write_all(format!("{}\n", command).as_bytes()).await
+            .map_err(|e| ConnectorError::BgpError(e.to_string()))?;
+        
+        let mut output = String::new();
+        while let Some(line) = lines.next_line().await.map_err(|e| ConnectorError::BgpError(e.to_string()))? {
+            output.push_str(&line);
+            output.push('\n');
+            // A reply ends with a line whose code is followed by a space
+            if is_final_reply(&line) {
+                if line.starts_with('8') || line.starts_with('9') {
+                    return Err(ConnectorError::BgpError(format!("BIRD: {}", line)));
+                }
+                break;
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[async_trait]
+impl BgpSessionSource for BirdSessionSource {
+    async fn sessions(&self) -> Result<Vec<BgpSessionStats>, ConnectorError> {
+        Ok(parse_bird_protocols(&self.command("show protocols all").await?))
+    }
+}
+
+fn is_final_reply(line: &str) -> bool {
+    reply_code(line).is_some_and(|(_, separator, _)| separator == b' ')
+}
+
+/// Split "NNNN-text" / "NNNN text" into code, separator and text
+fn reply_code(line: &str) -> Option<(&str, u8, &str)> {
+    let code = line.get(..4).filter(|c| c.bytes().all(|b| b.is_ascii_digit()))?;
+    let separator = *line.as_bytes().get(4)?;
+    Some((code, separator, line.get(5..)?))
+}
+
+/// Parse `show protocols all` output into BGP sessions
+pub fn parse_bird_protocols(output: &str) -> Vec<BgpSessionStats> {
+    let mut sessions = Vec::new();
+    let mut current: Option<BgpSessionStats> = None;
+    
+    for raw in output.lines() {
+        // Strip the "NNNN-" / "NNNN " reply code
+        let (code, line) = match reply_code(raw) {
+            Some((code, _, line)) => (Some(code), line),
+            None => (None, raw),
+        };
+        
+        // 1002: protocol summary line "name proto table state since info"
+        if code == Some("1002") {
+            sessions.extend(current.take());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(1).is_some_and(|p| p.eq_ignore_ascii_case("bgp")) {
+                current = Some(BgpSessionStats {
+                    name: fields[0].to_string(),
+                    neighbor: None,
+                    state: fields.last().map(|s| parse_bgp_state(s)).unwrap_or(BgpState::Idle),
+                    prefixes_received: 0,
+                    prefixes_advertised: 0,
+                });
+            }
+            continue;
+        }
+        
+        let Some(session) = current.as_mut() else { continue };
+        let line = line.trim();
+        if let Some(state) = line.strip_prefix("BGP state:") {
+            session.state = parse_bgp_state(state.trim());
+        } else if let Some(addr) = line.strip_prefix("Neighbor address:") {
+            session.neighbor = addr.trim().split('%').next().and_then(|a| a.parse().ok());
+        } else if let Some(routes) = line.strip_prefix("Routes:") {
+            // "10 imported, 5 exported, 10 preferred"; BIRD 2 lists one per channel
+            for part in routes.split(',') {
+                let mut words = part.split_whitespace();
+                let count = words.next().and_then(|n| n.parse::<u32>().ok()).unwrap_or(0);
+                match words.next() {
+                    Some("imported") => session.prefixes_received += count,
+                    Some("exported") => session.prefixes_advertised += count,
+                    _ => {}
+                }
+            }
+        }
+    }
+    
+    sessions.extend(current);
+    sessions
+}
+
+fn parse_bgp_state(state: &str) -> BgpState {
+    match state.to_ascii_lowercase().as_str() {
+        "established" => BgpState::Established,
+        "connect" => BgpState::Connect,
+        "active" => BgpState::Active,
+        "opensent" => BgpState::OpenSent,
+        "openconfirm" => BgpState::OpenConfirm,
+        _ => BgpState::Idle,
+    }
+}
+
+// =============================================================================
+// GoBGP
+// =============================================================================
+
+/// Reads sessions from `gobgp neighbor -j`
+pub struct GobgpSessionSource {
+    binary: String,
+    api_host: Option<String>,
+}
+
+impl GobgpSessionSource {
+    pub fn new() -> Self {
+        Self { binary: "gobgp".to_string(), api_host: None }
+    }
+    
+    pub fn with_binary(mut self, binary: impl Into<String>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+    
+    /// gobgpd API host when not local
+    pub fn with_api_host(mut self, host: impl Into<String>) -> Self {
+        self.api_host = Some(host.into());
+        self
+    }
+}
+
+impl Default for GobgpSessionSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BgpSessionSource for GobgpSessionSource {
+    async fn sessions(&self) -> Result<Vec<BgpSessionStats>, ConnectorError> {
+        let mut command = tokio::process::Command::new(&self.binary);
+        if let Some(host) = &self.api_host {
+            command.args(["-u", host]);
+        }
+        let output = command.args(["neighbor", "-j"]).output().await
+            .map_err(|e| ConnectorError::BgpError(format!("{}: {}", self.binary, e)))?;
+        if !output.status.success() {
+            return Err(ConnectorError::BgpError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        
+        let peers: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| ConnectorError::BgpError(format!("gobgp output: {}", e)))?;
+        Ok(peers.iter().map(parse_gobgp_peer).collect())
+    }
+}
+
+fn parse_gobgp_peer(peer: &serde_json::Value) -> BgpSessionStats {
+    let conf = &peer["conf"];
+    let neighbor = conf["neighbor_address"].as_str().and_then(|a| a.parse().ok());
+    
+    // Older releases emit the FSM state as a number, newer ones as a string
+    let state = match &peer["state"]["session_state"] {
+        serde_json::Value::Number(n) => match n.as_u64() {
+            Some(2) => BgpState::Connect,
+            Some(3) => BgpState::Active,
+            Some(4) => BgpState::OpenSent,
+            Some(5) => BgpState::OpenConfirm,
+            Some(6) => BgpState::Established,
+            _ => BgpState::Idle,
+        },
+        serde_json::Value::String(s) => parse_bgp_state(s.trim_start_matches("SESSION_STATE_")),
+        _ => BgpState::Idle,
+    };
+    
+    let (mut received, mut advertised) = (0, 0);
+    for afi in peer["afi_safis"].as_array().into_iter().flatten() {
+        let state = &afi["state"];
+        received += state["accepted"].as_u64().or_else(|| state["received"].as_u64()).unwrap_or(0) as u32;
+        advertised += state["advertised"].as_u64().unwrap_or(0) as u32;
+    }
+    
+    BgpSessionStats {
+        name: conf["description"].as_str()
+            .filter(|d| !d.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| conf["neighbor_address"].as_str().unwrap_or_default().to_string()),
+        neighbor,
+        state,
+        prefixes_received: received,
+        prefixes_advertised: advertised,
+    }
+}
+
+// =============================================================================
+// Probes
+// =============================================================================
+
+/// Times TCP handshakes to the cloud peer. A refused connection still
+/// answers with a RST, so only timeouts count as loss.
+pub struct TcpProber {
+    port: u16,
+    count: u32,
+    timeout: Duration,
+    spacing: Duration,
+}
+
+impl TcpProber {
+    pub fn new() -> Self {
+        Self {
+            port: 179,
+            count: 5,
+            timeout: Duration::from_secs(1),
+            spacing: Duration::from_millis(200),
+        }
+    }
+    
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+    
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = count.max(1);
+        self
+    }
+    
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for TcpProber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Prober for TcpProber {
+    async fn probe(&self, target: IpAddr) -> ProbeResult {
+        let addr = SocketAddr::new(target, self.port);
+        let mut rtts = Vec::with_capacity(self.count as usize);
+        
+        for i in 0..self.count {
+            if i > 0 {
+                tokio::time::sleep(self.spacing).await;
+            }
+            let start = Instant::now();
+            match tokio::time::timeout(self.timeout, tokio::net::TcpStream::connect(addr)).await {
+                Ok(Ok(_)) => rtts.push(start.elapsed().as_secs_f64() * 1000.0),
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                    rtts.push(start.elapsed().as_secs_f64() * 1000.0)
+                }
+                _ => {}
+            }
+        }
+        
+        summarize(&rtts, self.count)
+    }
+}
+
+/// Mean RTT, mean successive difference as jitter, and loss ratio
+pub fn summarize(rtts: &[f64], sent: u32) -> ProbeResult {
+    let packet_loss = 1.0 - rtts.len() as f64 / sent.max(1) as f64;
+    if rtts.is_empty() {
+        return ProbeResult { latency_ms: 0.0, jitter_ms: 0.0, packet_loss };
+    }
+    
+    let latency_ms = rtts.iter().sum::<f64>() / rtts.len() as f64;
+    let jitter_ms = if rtts.len() > 1 {
+        rtts.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (rtts.len() - 1) as f64
+    } else {
+        0.0
+    };
+    
+    ProbeResult { latency_ms, jitter_ms, packet_loss }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `show protocols all` from BIRD 2.0 over the control socket
+    const BIRD_PROTOCOLS: &str = "\
+2002-Name       Proto      Table      State  Since         Info
+1002-device1    Device     ---        up     2024-03-01 10:00:00  
+1006-
+1002-aws_vpn_1  BGP        ---        up     2024-03-01 10:00:05  Established   
+1006-  BGP state:          Established
+     Neighbor address: 169.254.10.1
+     Neighbor AS:      64512
+     Local AS:         65000
+     Hold timer:       21.412/30
+   Channel ipv4
+     State:          UP
+     Table:          master4
+     Routes:         12 imported, 4 exported, 12 preferred
+   Channel ipv6
+     State:          UP
+     Table:          master6
+     Routes:         3 imported, 1 exported, 3 preferred
+ 
+1002-azure_er   BGP        ---        start  2024-03-01 10:00:05  Active        Socket: Connection refused
+1006-  BGP state:          Active
+     Neighbor address: fe80::1%eth1
+     Neighbor AS:      12076
+     Last error:       Socket: Connection refused
+ 
+1002-kernel4    Kernel     master4    up     2024-03-01 10:00:00  
+1006-  Channel ipv4
+     Routes:         5 imported, 20 exported, 5 preferred
+0000 
+";
+
+    #[test]
+    fn test_parse_bird_protocols() {
+        let sessions = parse_bird_protocols(BIRD_PROTOCOLS);
+        assert_eq!(sessions.len(), 2);
+
+        let aws = &sessions[0];
+        assert_eq!(aws.name, "aws_vpn_1");
+        assert_eq!(aws.state, BgpState::Established);
+        assert_eq!(aws.neighbor, Some("169.254.10.1".parse().unwrap()));
+        assert_eq!((aws.prefixes_received, aws.prefixes_advertised), (15, 5));
+
+        let azure = &sessions[1];
+        assert_eq!(azure.name, "azure_er");
+        assert_eq!(azure.state, BgpState::Active);
+        assert_eq!(azure.neighbor, Some("fe80::1".parse().unwrap()));
+        assert_eq!((azure.prefixes_received, azure.prefixes_advertised), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_bird1_protocols() {
+        // BIRD 1.6 has no channels; routes are listed on the protocol
+        let output = "1002-aws_vpn_1 BGP      master   up     10:00:05    Established\n1006-  Preference:     100\n  Routes:         7 imported, 2 exported, 7 preferred\n0000 \n";
+        let sessions = parse_bird_protocols(output);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!((sessions[0].prefixes_received, sessions[0].prefixes_advertised), (7, 2));
+    }
+
+    #[test]
+    fn test_short_and_non_ascii_lines_do_not_panic() {
+        let output = "\n1\n1002\n1002-\n123é BGP\n1002é\n1002-x BGP --- up now Established\n1006-  BGP state: Connect\n  Routes: ñ imported\n";
+        let sessions = parse_bird_protocols(output);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].state, BgpState::Connect);
+
+        for line in ["", "0", "0000", "123é", "1002é", "abcd ", "ü000 "] {
+            assert!(!is_final_reply(line), "{:?}", line);
+        }
+        assert!(is_final_reply("0000 "));
+        assert!(is_final_reply("8001 Reply too long"));
+        assert!(!is_final_reply("1002-aws_vpn_1 BGP"));
+    }
+}
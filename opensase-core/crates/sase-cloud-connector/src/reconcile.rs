@@ -0,0 +1,542 @@
+//! Declarative reconciliation of cloud connections
+//!
+//! Terraform-style workflow: a tenant's desired connections are declared in a
+//! [`TenantSpec`], [`Reconciler::plan`] diffs it against live state, and
+//! [`Reconciler::apply`] converges by creating, updating, replacing and
+//! deleting connections. Connections are matched to the spec by name.
+//!
+//! Plans record a fingerprint of the state they were computed against and
+//! are refused if that state has since changed.
+
+use crate::{
+    BgpConfig, CloudConnection, CloudConnectorService, CloudRoute, ConnectionStatus,
+    ConnectionType, ConnectorError, CreateConnectionRequest,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Desired cloud connections for one tenant
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TenantSpec {
+    pub tenant_id: Uuid,
+    /// Desired connections, keyed by name
+    pub connections: Vec<CreateConnectionRequest>,
+    /// Also delete connections this reconciler did not create
+    #[serde(default)]
+    pub prune: bool,
+}
+
+impl TenantSpec {
+    /// Parse a spec from JSON
+    pub fn from_json(json: &str) -> Result<Self, ConnectorError> {
+        serde_json::from_str(json)
+            .map_err(|e| ConnectorError::ValidationError(format!("Invalid spec: {}", e)))
+    }
+    
+    fn validate(&self) -> Result<(), ConnectorError> {
+        let mut names = HashSet::new();
+        for conn in &self.connections {
+            if conn.name.is_empty() {
+                return Err(ConnectorError::ValidationError("Connection name is required".to_string()));
+            }
+            if !names.insert(conn.name.as_str()) {
+                return Err(ConnectorError::ValidationError(format!("Duplicate connection name: {}", conn.name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single attribute difference between state and spec
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub current: String,
+    pub desired: String,
+    /// Change cannot be made in place; the connection must be replaced
+    pub force_new: bool,
+}
+
+/// Action in a reconcile plan
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlannedAction {
+    Create {
+        name: String,
+        spec: CreateConnectionRequest,
+    },
+    Update {
+        id: Uuid,
+        name: String,
+        changes: Vec<FieldChange>,
+        spec: CreateConnectionRequest,
+    },
+    /// Create the new connection, move static routes, then delete the old one
+    Replace {
+        id: Uuid,
+        name: String,
+        changes: Vec<FieldChange>,
+        spec: CreateConnectionRequest,
+    },
+    Delete {
+        id: Uuid,
+        name: String,
+    },
+}
+
+impl PlannedAction {
+    /// Connection name
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Create { name, .. }
+            | Self::Update { name, .. }
+            | Self::Replace { name, .. }
+            | Self::Delete { name, .. } => name,
+        }
+    }
+    
+    /// One-line summary
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Create { name, spec } => format!(
+                "+ create {} ({} {} @ {})",
+                name, spec.cloud_provider.as_str(), spec.cloud_region, spec.pop_location
+            ),
+            Self::Update { name, changes, .. } => format!("~ update {} ({} changes)", name, changes.len()),
+            Self::Replace { name, changes, .. } => format!(
+                "-/+ replace {} (forced by {})",
+                name,
+                changes.iter().filter(|c| c.force_new).map(|c| c.field.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+            Self::Delete { name, id } => format!("- delete {} ({})", name, id),
+        }
+    }
+}
+
+/// Reconcile plan for one tenant
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReconcilePlan {
+    pub tenant_id: Uuid,
+    pub actions: Vec<PlannedAction>,
+    /// Connections already matching the spec
+    pub unchanged: Vec<String>,
+    /// Connections not in the spec that will be left alone
+    pub unmanaged: Vec<(Uuid, String)>,
+    /// Fingerprint of the state the plan was computed against
+    pub state_fingerprint: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ReconcilePlan {
+    /// Nothing to do
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+    
+    /// Human-readable plan
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for action in &self.actions {
+            out.push_str(&action.summary());
+            out.push('\n');
+            if let PlannedAction::Update { changes, .. } | PlannedAction::Replace { changes, .. } = action {
+                for c in changes {
+                    out.push_str(&format!(
+                        "    {}: {} -> {}{}\n",
+                        c.field, c.current, c.desired,
+                        if c.force_new { " (forces replacement)" } else { "" }
+                    ));
+                }
+            }
+        }
+        
+        let count = |f: fn(&PlannedAction) -> bool| self.actions.iter().filter(|a| f(a)).count();
+        out.push_str(&format!(
+            "Plan: {} to add, {} to change, {} to replace, {} to destroy. {} unchanged, {} unmanaged.\n",
+            count(|a| matches!(a, PlannedAction::Create { .. })),
+            count(|a| matches!(a, PlannedAction::Update { .. })),
+            count(|a| matches!(a, PlannedAction::Replace { .. })),
+            count(|a| matches!(a, PlannedAction::Delete { .. })),
+            self.unchanged.len(),
+            self.unmanaged.len(),
+        ));
+        out
+    }
+}
+
+/// Outcome of a successfully applied action
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AppliedAction {
+    pub name: String,
+    pub summary: String,
+    pub connection_id: Uuid,
+}
+
+/// Action that failed during apply
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailedAction {
+    pub name: String,
+    pub summary: String,
+    pub error: String,
+}
+
+/// Result of applying a plan
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ApplyResult {
+    pub applied: Vec<AppliedAction>,
+    pub failed: Vec<FailedAction>,
+}
+
+impl ApplyResult {
+    /// Every action succeeded
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Drift between the spec and live state
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DriftReport {
+    pub tenant_id: Uuid,
+    pub checked_at: DateTime<Utc>,
+    /// In the spec but not deployed
+    pub missing: Vec<String>,
+    /// Deployed with different settings
+    pub modified: Vec<DriftedConnection>,
+    /// Managed by the reconciler but removed from the spec
+    pub orphaned: Vec<(Uuid, String)>,
+    /// Deployed outside the reconciler and not in the spec
+    pub unmanaged: Vec<(Uuid, String)>,
+    /// In the spec and deployed, but not healthy
+    pub unhealthy: Vec<(Uuid, String, ConnectionStatus)>,
+}
+
+impl DriftReport {
+    /// State differs from the spec
+    pub fn has_drift(&self) -> bool {
+        !self.missing.is_empty() || !self.modified.is_empty() || !self.orphaned.is_empty()
+    }
+}
+
+/// Connection whose settings differ from the spec
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DriftedConnection {
+    pub id: Uuid,
+    pub name: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Declarative reconciler for cloud connections
+pub struct Reconciler {
+    service: Arc<CloudConnectorService>,
+    /// Connections created or adopted by the reconciler: id -> (tenant, name)
+    managed: dashmap::DashMap<Uuid, (Uuid, String)>,
+}
+
+impl Reconciler {
+    pub fn new(service: Arc<CloudConnectorService>) -> Self {
+        Self {
+            service,
+            managed: dashmap::DashMap::new(),
+        }
+    }
+    
+    /// Whether a connection is managed by the reconciler
+    pub fn is_managed(&self, id: &Uuid) -> bool {
+        self.managed.contains_key(id)
+    }
+    
+    /// Diff a spec against live state
+    pub fn plan(&self, spec: &TenantSpec) -> Result<ReconcilePlan, ConnectorError> {
+        spec.validate()?;
+        
+        let current = self.current_state(&spec.tenant_id);
+        let matched = match_by_name(spec, &current);
+        let mut plan = ReconcilePlan {
+            tenant_id: spec.tenant_id,
+            actions: Vec::new(),
+            unchanged: Vec::new(),
+            unmanaged: Vec::new(),
+            state_fingerprint: fingerprint(&current),
+            created_at: Utc::now(),
+        };
+        
+        for desired in &spec.connections {
+            match matched.get(desired.name.as_str()) {
+                None => plan.actions.push(PlannedAction::Create {
+                    name: desired.name.clone(),
+                    spec: desired.clone(),
+                }),
+                Some(conn) => {
+                    let changes = diff(conn, desired);
+                    if changes.is_empty() {
+                        plan.unchanged.push(desired.name.clone());
+                    } else if changes.iter().any(|c| c.force_new) {
+                        plan.actions.push(PlannedAction::Replace {
+                            id: conn.id,
+                            name: desired.name.clone(),
+                            changes,
+                            spec: desired.clone(),
+                        });
+                    } else {
+                        plan.actions.push(PlannedAction::Update {
+                            id: conn.id,
+                            name: desired.name.clone(),
+                            changes,
+                            spec: desired.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        
+        let matched_ids: HashSet<Uuid> = matched.values().map(|c| c.id).collect();
+        for conn in current.iter().filter(|c| !matched_ids.contains(&c.id)) {
+            if self.is_deletable(conn, spec.prune) {
+                plan.actions.push(PlannedAction::Delete { id: conn.id, name: conn.name.clone() });
+            } else {
+                plan.unmanaged.push((conn.id, conn.name.clone()));
+            }
+        }
+        
+        Ok(plan)
+    }
+    
+    /// Apply a plan; refuses plans computed against stale state
+    ///
+    /// Creates and replacements run before deletions so capacity is never
+    /// removed first. Failed actions are reported and the rest continue.
+    pub async fn apply(&self, plan: &ReconcilePlan) -> Result<ApplyResult, ConnectorError> {
+        let current = self.current_state(&plan.tenant_id);
+        if fingerprint(&current) != plan.state_fingerprint {
+            return Err(ConnectorError::ValidationError(
+                "State changed since the plan was created; re-run plan".to_string(),
+            ));
+        }
+        
+        let mut result = ApplyResult::default();
+        let (deletes, others): (Vec<_>, Vec<_>) = plan.actions.iter()
+            .partition(|a| matches!(a, PlannedAction::Delete { .. }));
+        
+        for action in others.into_iter().chain(deletes) {
+            match self.apply_action(plan.tenant_id, action).await {
+                Ok(connection_id) => {
+                    tracing::info!("Reconcile {}: {}", plan.tenant_id, action.summary());
+                    result.applied.push(AppliedAction {
+                        name: action.name().to_string(),
+                        summary: action.summary(),
+                        connection_id,
+                    });
+                }
+                Err(e) => {
+                    tracing::error!("Reconcile {} failed: {}: {}", plan.tenant_id, action.summary(), e);
+                    result.failed.push(FailedAction {
+                        name: action.name().to_string(),
+                        summary: action.summary(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// Plan and apply in one step
+    pub async fn converge(&self, spec: &TenantSpec) -> Result<ApplyResult, ConnectorError> {
+        let plan = self.plan(spec)?;
+        if plan.is_empty() {
+            return Ok(ApplyResult::default());
+        }
+        self.apply(&plan).await
+    }
+    
+    /// Report drift between the spec and live state without changing anything
+    pub fn detect_drift(&self, spec: &TenantSpec) -> Result<DriftReport, ConnectorError> {
+        spec.validate()?;
+        
+        let current = self.current_state(&spec.tenant_id);
+        let matched = match_by_name(spec, &current);
+        let mut report = DriftReport {
+            tenant_id: spec.tenant_id,
+            checked_at: Utc::now(),
+            missing: Vec::new(),
+            modified: Vec::new(),
+            orphaned: Vec::new(),
+            unmanaged: Vec::new(),
+            unhealthy: Vec::new(),
+        };
+        
+        for desired in &spec.connections {
+            let Some(conn) = matched.get(desired.name.as_str()) else {
+                report.missing.push(desired.name.clone());
+                continue;
+            };
+            let changes = diff(conn, desired);
+            if !changes.is_empty() {
+                report.modified.push(DriftedConnection { id: conn.id, name: conn.name.clone(), changes });
+            }
+            if matches!(conn.status, ConnectionStatus::Degraded | ConnectionStatus::Down) {
+                report.unhealthy.push((conn.id, conn.name.clone(), conn.status));
+            }
+        }
+        
+        let matched_ids: HashSet<Uuid> = matched.values().map(|c| c.id).collect();
+        for conn in current.iter().filter(|c| !matched_ids.contains(&c.id)) {
+            if self.is_managed(&conn.id) {
+                report.orphaned.push((conn.id, conn.name.clone()));
+            } else {
+                report.unmanaged.push((conn.id, conn.name.clone()));
+            }
+        }
+        
+        if report.has_drift() {
+            tracing::warn!(
+                "Drift for tenant {}: {} missing, {} modified, {} orphaned",
+                spec.tenant_id, report.missing.len(), report.modified.len(), report.orphaned.len()
+            );
+        }
+        Ok(report)
+    }
+    
+    async fn apply_action(&self, tenant_id: Uuid, action: &PlannedAction) -> Result<Uuid, ConnectorError> {
+        match action {
+            PlannedAction::Create { name, spec } => {
+                let conn = self.service.create_connection(tenant_id, spec.clone()).await?;
+                self.managed.insert(conn.id, (tenant_id, name.clone()));
+                Ok(conn.id)
+            }
+            PlannedAction::Update { id, name, spec, .. } => {
+                let conn = self.service.update_connection(id, spec.clone()).await?;
+                self.managed.insert(conn.id, (tenant_id, name.clone()));
+                Ok(conn.id)
+            }
+            PlannedAction::Replace { id, name, spec, .. } => {
+                let old = self.service.get_connection(id)
+                    .ok_or(ConnectorError::ConnectionNotFound(*id))?;
+                let new = self.service.create_connection(tenant_id, spec.clone()).await?;
+                self.managed.insert(new.id, (tenant_id, name.clone()));
+                
+                // Static routes follow the connection to its new peer
+                for route in &old.routes {
+                    self.service.add_route(&new.id, CloudRoute {
+                        next_hop: new.bgp_config.cloud_ip,
+                        connection_id: new.id,
+                        ..route.clone()
+                    }).await?;
+                }
+                self.delete(&old).await?;
+                Ok(new.id)
+            }
+            PlannedAction::Delete { id, .. } => {
+                let conn = self.service.get_connection(id)
+                    .ok_or(ConnectorError::ConnectionNotFound(*id))?;
+                self.delete(&conn).await?;
+                Ok(*id)
+            }
+        }
+    }
+    
+    async fn delete(&self, conn: &CloudConnection) -> Result<(), ConnectorError> {
+        for route in &conn.routes {
+            self.service.route_manager().remove_route(&route.prefix, &conn.id);
+        }
+        self.service.delete_connection(&conn.id).await?;
+        self.managed.remove(&conn.id);
+        Ok(())
+    }
+    
+    fn is_deletable(&self, conn: &CloudConnection, prune: bool) -> bool {
+        // VPN fallback tunnels come and go with primary health
+        if matches!(conn.connection_type, ConnectionType::VpnTunnel { .. }) && !self.is_managed(&conn.id) {
+            return false;
+        }
+        self.is_managed(&conn.id) || prune
+    }
+    
+    fn current_state(&self, tenant_id: &Uuid) -> Vec<CloudConnection> {
+        let mut current: Vec<CloudConnection> = self.service.list_connections(tenant_id)
+            .into_iter()
+            .filter(|c| !matches!(c.status, ConnectionStatus::Deleting | ConnectionStatus::Deleted))
+            .collect();
+        current.sort_by_key(|c| (c.created_at, c.id));
+        current
+    }
+}
+
+/// Match spec entries to live connections by name (oldest wins on duplicates)
+fn match_by_name<'a>(spec: &TenantSpec, current: &'a [CloudConnection]) -> HashMap<&'a str, &'a CloudConnection> {
+    let wanted: HashSet<&str> = spec.connections.iter().map(|c| c.name.as_str()).collect();
+    let mut matched = HashMap::new();
+    for conn in current {
+        if wanted.contains(conn.name.as_str()) {
+            matched.entry(conn.name.as_str()).or_insert(conn);
+        }
+    }
+    matched
+}
+
+/// Attribute differences between a live connection and its spec
+fn diff(current: &CloudConnection, desired: &CreateConnectionRequest) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    let mut check = |field: &str, current: String, desired: String, force_new: bool| {
+        if current != desired {
+            changes.push(FieldChange { field: field.to_string(), current, desired, force_new });
+        }
+    };
+    
+    // Provider resources that cannot be modified in place
+    check("cloud_provider", current.cloud_provider.as_str().to_string(), desired.cloud_provider.as_str().to_string(), true);
+    check("connection_type", connection_type_str(&current.connection_type), connection_type_str(&desired.connection_type), true);
+    check("pop_location", current.pop_location.clone(), desired.pop_location.clone(), true);
+    check("cloud_region", current.cloud_region.clone(), desired.cloud_region.clone(), true);
+    check("vlan_id", current.vlan_id.to_string(), desired.vlan_id.to_string(), true);
+    
+    let (cur, des) = (&current.bgp_config, &desired.bgp_config);
+    check("bgp.our_asn", cur.our_asn.to_string(), des.our_asn.to_string(), true);
+    check("bgp.cloud_asn", cur.cloud_asn.to_string(), des.cloud_asn.to_string(), true);
+    check("bgp.our_ip", cur.our_ip.to_string(), des.our_ip.to_string(), true);
+    check("bgp.cloud_ip", cur.cloud_ip.to_string(), des.cloud_ip.to_string(), true);
+    
+    // In-place changes
+    check("bandwidth_mbps", current.bandwidth_mbps.to_string(), desired.bandwidth_mbps.to_string(), false);
+    check("bgp.advertised_prefixes", prefixes_str(cur), prefixes_str(des), false);
+    check("bgp.local_preference", cur.local_preference.to_string(), des.local_preference.to_string(), false);
+    check("bgp.med", cur.med.to_string(), des.med.to_string(), false);
+    if cur.md5_auth != des.md5_auth {
+        let redact = |v: &Option<String>| if v.is_some() { "(sensitive)" } else { "(none)" }.to_string();
+        check("bgp.md5_auth", redact(&cur.md5_auth), format!("{} (changed)", redact(&des.md5_auth)), false);
+    }
+    
+    changes
+}
+
+fn connection_type_str(connection_type: &ConnectionType) -> String {
+    serde_json::to_string(connection_type).unwrap_or_default()
+}
+
+fn prefixes_str(bgp: &BgpConfig) -> String {
+    let prefixes: BTreeSet<String> = bgp.advertised_prefixes.iter().map(|p| p.to_string()).collect();
+    format!("[{}]", prefixes.into_iter().collect::<Vec<_>>().join(", "))
+}
+
+/// Fingerprint of the configuration-relevant state (ignores health churn)
+fn fingerprint(current: &[CloudConnection]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for conn in current {
+        conn.id.hash(&mut hasher);
+        conn.name.hash(&mut hasher);
+        connection_type_str(&conn.connection_type).hash(&mut hasher);
+        (conn.cloud_provider, conn.bandwidth_mbps, conn.vlan_id).hash(&mut hasher);
+        (&conn.pop_location, &conn.cloud_region).hash(&mut hasher);
+        let bgp = &conn.bgp_config;
+        (bgp.our_asn, bgp.cloud_asn, bgp.our_ip, bgp.cloud_ip, &bgp.md5_auth).hash(&mut hasher);
+        (prefixes_str(bgp), bgp.local_preference, bgp.med).hash(&mut hasher);
+    }
+    hasher.finish()
+}
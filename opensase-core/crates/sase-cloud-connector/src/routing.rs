@@ -1,17 +1,28 @@
 //! Multi-cloud routing management
 //!
-//! Handles route management across multiple cloud connections with failover support.
+//! Handles route management across multiple cloud connections with failover support,
+//! ECMP/weighted multipath across connections to the same provider and region, and
+//! pushing the resulting forwarding table to VPP.
 
-use crate::{CloudConnection, CloudRoute, ConnectorError};
+use crate::{CloudConnection, CloudRoute, ConnectionStatus, ConnectorError};
+use async_trait::async_trait;
 use ipnet::IpNet;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use parking_lot::RwLock;
 
+/// Largest path weight VPP accepts
+const MAX_VPP_WEIGHT: u32 = 255;
+
 /// Cloud route manager
 pub struct CloudRouteManager {
     routes: Arc<RwLock<RouteTable>>,
     failover_pairs: Arc<RwLock<HashMap<uuid::Uuid, uuid::Uuid>>>,
+    flow_hash: Arc<RwLock<FlowHashConfig>>,
+    bulk_preference: Arc<RwLock<Option<uuid::Uuid>>>,
 }
 
 /// Route table
@@ -51,6 +62,97 @@ pub enum LoadBalancing {
     RoundRobin,
     Weighted(HashMap<uuid::Uuid, u32>),
     LeastConnections,
+    /// Equal-cost multipath, one share per connection
+    Ecmp,
+    /// Weights proportional to provisioned bandwidth
+    BandwidthAware,
+}
+
+/// Traffic class for cost-aware steering
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrafficClass {
+    /// Follows the normal best/multipath routes
+    LatencySensitive,
+    /// May be steered over the cheapest viable connection
+    Bulk,
+}
+
+/// Fields hashed to pin a flow to one path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlowHashConfig {
+    pub src: bool,
+    pub dst: bool,
+    pub sport: bool,
+    pub dport: bool,
+    pub proto: bool,
+    /// Hash both directions of a flow to the same path
+    pub symmetric: bool,
+}
+
+impl Default for FlowHashConfig {
+    fn default() -> Self {
+        Self { src: true, dst: true, sport: true, dport: true, proto: true, symmetric: false }
+    }
+}
+
+impl FlowHashConfig {
+    /// Hash on addresses only (keeps fragments and ICMP on the same path)
+    pub fn addresses_only() -> Self {
+        Self { sport: false, dport: false, proto: false, ..Self::default() }
+    }
+    
+    /// VPP command applying this config to a FIB table
+    pub fn vpp_command(&self, table_id: u32) -> String {
+        let fields = [
+            (self.src, "src"),
+            (self.dst, "dst"),
+            (self.sport, "sport"),
+            (self.dport, "dport"),
+            (self.proto, "proto"),
+            (self.symmetric, "symmetric"),
+        ];
+        let mut command = format!("set ip flow-hash table {}", table_id);
+        for (_, name) in fields.iter().filter(|(on, _)| *on) {
+            command.push(' ');
+            command.push_str(name);
+        }
+        command
+    }
+    
+    fn hash(&self, flow: &FlowTuple) -> u64 {
+        let (mut a, mut b) = (flow.src_ip, flow.dst_ip);
+        let (mut pa, mut pb) = (flow.src_port, flow.dst_port);
+        if self.symmetric && (a, pa) > (b, pb) {
+            std::mem::swap(&mut a, &mut b);
+            std::mem::swap(&mut pa, &mut pb);
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        if self.src { a.hash(&mut hasher); }
+        if self.dst { b.hash(&mut hasher); }
+        if self.sport { pa.hash(&mut hasher); }
+        if self.dport { pb.hash(&mut hasher); }
+        if self.proto { flow.protocol.hash(&mut hasher); }
+        hasher.finish()
+    }
+}
+
+/// Flow identity used for path selection
+#[derive(Clone, Copy, Debug)]
+pub struct FlowTuple {
+    pub src_ip: IpAddr,
+    pub dst_ip: IpAddr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+}
+
+/// One forwarding path for a prefix
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FibPath {
+    pub next_hop: IpAddr,
+    pub connection_id: uuid::Uuid,
+    /// Normalized weight, 1-255
+    pub weight: u32,
 }
 
 impl CloudRouteManager {
@@ -58,6 +160,8 @@ impl CloudRouteManager {
         Self {
             routes: Arc::new(RwLock::new(RouteTable::default())),
             failover_pairs: Arc::new(RwLock::new(HashMap::new())),
+            flow_hash: Arc::new(RwLock::new(FlowHashConfig::default())),
+            bulk_preference: Arc::new(RwLock::new(None)),
         }
     }
     
@@ -159,7 +263,7 @@ impl CloudRouteManager {
             }
             RoutingStrategy::ActiveActive { connections, load_balancing } => {
                 match load_balancing {
-                    LoadBalancing::RoundRobin => {
+                    LoadBalancing::RoundRobin | LoadBalancing::Ecmp => {
                         // Equal weights
                         let weight = 100 / connections.len() as u32;
                         for conn_id in connections {
@@ -174,6 +278,9 @@ impl CloudRouteManager {
                     LoadBalancing::LeastConnections => {
                         // Would need connection tracking
                     }
+                    LoadBalancing::BandwidthAware => {
+                        // Needs connection bandwidth, see configure_multipath
+                    }
                 }
             }
             RoutingStrategy::LatencyBased { connections: _ } => {
@@ -214,6 +321,188 @@ impl CloudRouteManager {
         }
     }
     
+    /// Spread traffic across several active connections to the same provider/region
+    ///
+    /// The connections' routes get equal priority (the highest any of them
+    /// already has) so they form one multipath set per prefix, weighted by
+    /// `balancing`.
+    pub fn configure_multipath(
+        &self,
+        connections: &[CloudConnection],
+        balancing: &LoadBalancing,
+    ) -> Result<(), ConnectorError> {
+        let Some(first) = connections.first() else {
+            return Err(ConnectorError::ValidationError("No connections to balance across".to_string()));
+        };
+        if let Some(other) = connections.iter().find(|c| {
+            c.cloud_provider != first.cloud_provider || c.cloud_region != first.cloud_region
+        }) {
+            return Err(ConnectorError::ValidationError(format!(
+                "{} is in {} {}, expected {} {}",
+                other.name, other.cloud_provider.as_str(), other.cloud_region,
+                first.cloud_provider.as_str(), first.cloud_region
+            )));
+        }
+        if let Some(inactive) = connections.iter().find(|c| c.status != ConnectionStatus::Active) {
+            return Err(ConnectorError::ValidationError(format!("{} is not active", inactive.name)));
+        }
+        
+        let weights: HashMap<uuid::Uuid, u32> = match balancing {
+            LoadBalancing::RoundRobin | LoadBalancing::Ecmp => {
+                connections.iter().map(|c| (c.id, 1)).collect()
+            }
+            LoadBalancing::Weighted(weights) => connections.iter()
+                .map(|c| weights.get(&c.id).copied().filter(|w| *w > 0).map(|w| (c.id, w))
+                    .ok_or_else(|| ConnectorError::ValidationError(format!("No weight for {}", c.name))))
+                .collect::<Result<_, _>>()?,
+            LoadBalancing::BandwidthAware => {
+                connections.iter().map(|c| (c.id, c.bandwidth_mbps.max(1))).collect()
+            }
+            LoadBalancing::LeastConnections => {
+                return Err(ConnectorError::ValidationError(
+                    "Least-connections is not supported for multipath routes".to_string(),
+                ));
+            }
+        };
+        
+        let mut table = self.routes.write();
+        let priority = table.routes.values()
+            .flatten()
+            .filter(|r| weights.contains_key(&r.connection_id))
+            .map(|r| r.priority)
+            .max()
+            .unwrap_or(100);
+        for routes in table.routes.values_mut() {
+            for route in routes.iter_mut() {
+                if let Some(weight) = weights.get(&route.connection_id) {
+                    route.priority = priority;
+                    route.weight = *weight;
+                    route.active = true;
+                }
+            }
+        }
+        
+        tracing::info!(
+            "Multipath across {} {} connections in {} ({:?})",
+            connections.len(), first.cloud_provider.as_str(), first.cloud_region, balancing
+        );
+        Ok(())
+    }
+    
+    /// Take a failed connection's routes out of service
+    ///
+    /// Returns true when every affected prefix still has an active route,
+    /// i.e. remaining multipath members absorb the traffic and no backup
+    /// needs promoting.
+    pub fn withdraw_connection(&self, connection_id: &uuid::Uuid) -> bool {
+        let mut table = self.routes.write();
+        let mut covered = true;
+        for routes in table.routes.values_mut() {
+            if !routes.iter().any(|r| r.connection_id == *connection_id && r.active) {
+                continue;
+            }
+            for route in routes.iter_mut().filter(|r| r.connection_id == *connection_id) {
+                route.active = false;
+            }
+            covered &= routes.iter().any(|r| r.active);
+        }
+        covered
+    }
+    
+    /// Put a recovered connection's routes back in service
+    pub fn restore_connection(&self, connection_id: &uuid::Uuid) {
+        let mut table = self.routes.write();
+        for route in table.routes.values_mut().flatten().filter(|r| r.connection_id == *connection_id) {
+            route.active = true;
+        }
+    }
+    
+    /// Set the per-flow hash fields used for multipath
+    pub fn set_flow_hash(&self, config: FlowHashConfig) {
+        *self.flow_hash.write() = config;
+    }
+    
+    /// Current per-flow hash fields
+    pub fn flow_hash(&self) -> FlowHashConfig {
+        *self.flow_hash.read()
+    }
+    
+    /// Forwarding table: the active, highest-priority path set per prefix
+    pub fn forwarding_table(&self) -> BTreeMap<IpNet, Vec<FibPath>> {
+        let table = self.routes.read();
+        table.routes.iter()
+            .filter_map(|(prefix, routes)| {
+                let paths = multipath_set(routes);
+                (!paths.is_empty()).then_some((*prefix, paths))
+            })
+            .collect()
+    }
+    
+    /// Steer bulk traffic over a connection (`None` = follow normal routes)
+    pub fn set_bulk_preference(&self, connection_id: Option<uuid::Uuid>) {
+        *self.bulk_preference.write() = connection_id;
+    }
+    
+    /// Connection bulk traffic is steered over
+    pub fn bulk_preference(&self) -> Option<uuid::Uuid> {
+        *self.bulk_preference.read()
+    }
+    
+    /// Best route for a destination and traffic class
+    ///
+    /// Bulk traffic uses the preferred connection wherever it has an active
+    /// route for the longest matching prefix.
+    pub fn get_best_route_for(&self, destination: &IpNet, class: TrafficClass) -> Option<CloudRoute> {
+        if let (TrafficClass::Bulk, Some(preferred)) = (class, self.bulk_preference()) {
+            let table = self.routes.read();
+            let steered = table.routes.iter()
+                .filter(|(prefix, _)| prefix.contains(destination))
+                .max_by_key(|(prefix, _)| prefix.prefix_len())
+                .and_then(|(_, routes)| routes.iter().find(|r| r.active && r.connection_id == preferred));
+            if let Some(route) = steered {
+                return Some(route.clone());
+            }
+        }
+        self.get_best_route(destination)
+    }
+    
+    /// Forwarding table for a traffic class
+    pub fn forwarding_table_for(&self, class: TrafficClass) -> BTreeMap<IpNet, Vec<FibPath>> {
+        let mut fib = self.forwarding_table();
+        let (TrafficClass::Bulk, Some(preferred)) = (class, self.bulk_preference()) else {
+            return fib;
+        };
+        
+        let table = self.routes.read();
+        for (prefix, routes) in &table.routes {
+            if let Some(route) = routes.iter().find(|r| r.active && r.connection_id == preferred) {
+                fib.insert(*prefix, vec![FibPath { next_hop: route.next_hop, connection_id: preferred, weight: 1 }]);
+            }
+        }
+        fib
+    }
+    
+    /// Path a flow takes, mirroring the data plane's weighted flow hashing
+    pub fn select_path(&self, flow: &FlowTuple) -> Option<FibPath> {
+        let destination = IpNet::from(flow.dst_ip);
+        let table = self.routes.read();
+        let (_, routes) = table.routes.iter()
+            .filter(|(prefix, routes)| prefix.contains(&destination) && routes.iter().any(|r| r.active))
+            .max_by_key(|(prefix, _)| prefix.prefix_len())?;
+        
+        let paths = multipath_set(routes);
+        let total: u64 = paths.iter().map(|p| p.weight as u64).sum();
+        let mut bucket = self.flow_hash.read().hash(flow) % total.max(1);
+        paths.into_iter().find(|p| {
+            if bucket < p.weight as u64 {
+                true
+            } else {
+                bucket -= p.weight as u64;
+                false
+            }
+        })
+    }
+    
     /// Generate BIRD route filter
     pub fn generate_bird_filter(&self, tenant_prefix: &str) -> String {
         let table = self.routes.read();
@@ -237,3 +526,193 @@ impl Default for CloudRouteManager {
         Self::new()
     }
 }
+
+/// Active routes at the best priority, with weights scaled to VPP's range
+fn multipath_set(routes: &[CloudRoute]) -> Vec<FibPath> {
+    let Some(best) = routes.iter().filter(|r| r.active).map(|r| r.priority).max() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<FibPath> = routes.iter()
+        .filter(|r| r.active && r.priority == best)
+        .map(|r| FibPath { next_hop: r.next_hop, connection_id: r.connection_id, weight: r.weight.max(1) })
+        .collect();
+    paths.sort_by(|a, b| a.next_hop.cmp(&b.next_hop).then(a.connection_id.cmp(&b.connection_id)));
+    
+    let divisor = paths.iter().map(|p| p.weight).fold(0, gcd);
+    let max = paths.iter().map(|p| p.weight / divisor).max().unwrap_or(1);
+    for path in &mut paths {
+        let weight = path.weight / divisor;
+        path.weight = if max > MAX_VPP_WEIGHT {
+            ((weight as u64 * MAX_VPP_WEIGHT as u64) / max as u64).max(1) as u32
+        } else {
+            weight
+        };
+    }
+    paths
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// =============================================================================
+// VPP FIB Sync
+// =============================================================================
+
+/// Executes a batch of VPP CLI commands as one unit
+#[async_trait]
+pub trait VppCommandExecutor: Send + Sync {
+    async fn execute(&self, commands: &[String]) -> Result<(), ConnectorError>;
+}
+
+/// Runs batches through `vppctl exec`, so a whole update is applied in a
+/// single CLI session
+pub struct VppctlExecutor {
+    binary: String,
+    socket: String,
+    script_dir: PathBuf,
+}
+
+impl VppctlExecutor {
+    pub fn new() -> Self {
+        Self {
+            binary: "vppctl".to_string(),
+            socket: "/run/vpp/cli.sock".to_string(),
+            script_dir: std::env::temp_dir(),
+        }
+    }
+    
+    pub fn with_binary(mut self, binary: impl Into<String>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+    
+    pub fn with_socket(mut self, socket: impl Into<String>) -> Self {
+        self.socket = socket.into();
+        self
+    }
+}
+
+impl Default for VppctlExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl VppCommandExecutor for VppctlExecutor {
+    async fn execute(&self, commands: &[String]) -> Result<(), ConnectorError> {
+        let script = self.script_dir.join(format!("cloud-routes-{}.vpp", uuid::Uuid::new_v4()));
+        tokio::fs::write(&script, commands.join("\n") + "\n").await
+            .map_err(|e| ConnectorError::ProviderError(format!("writing VPP script: {}", e)))?;
+        
+        let output = tokio::process::Command::new(&self.binary)
+            .args(["-s", &self.socket, "exec"])
+            .arg(&script)
+            .output()
+            .await;
+        let _ = tokio::fs::remove_file(&script).await;
+        
+        let output = output.map_err(|e| ConnectorError::ProviderError(format!("{}: {}", self.binary, e)))?;
+        // vppctl exits 0 even when a command fails, so check its output too
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || stdout.contains("unknown input") || stdout.contains("failed") {
+            return Err(ConnectorError::ProviderError(format!(
+                "vppctl exec: {}{}",
+                stdout.trim(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Pushes the route manager's forwarding table to a VPP FIB table
+///
+/// Each push diffs against the last table VPP accepted. Per prefix, new
+/// and re-weighted paths are added before stale ones are removed, so a
+/// prefix never loses all its paths mid-update, and the whole diff goes
+/// out as one batch. The pushed state only advances when VPP accepts it.
+pub struct VppRouteSync {
+    executor: Arc<dyn VppCommandExecutor>,
+    table_id: u32,
+    class: TrafficClass,
+    pushed: tokio::sync::Mutex<PushedState>,
+}
+
+#[derive(Default)]
+struct PushedState {
+    routes: BTreeMap<IpNet, Vec<FibPath>>,
+    flow_hash: Option<FlowHashConfig>,
+}
+
+impl VppRouteSync {
+    pub fn new(executor: Arc<dyn VppCommandExecutor>) -> Self {
+        Self {
+            executor,
+            table_id: 0,
+            class: TrafficClass::LatencySensitive,
+            pushed: tokio::sync::Mutex::new(PushedState::default()),
+        }
+    }
+    
+    pub fn with_table(mut self, table_id: u32) -> Self {
+        self.table_id = table_id;
+        self
+    }
+    
+    /// Sync the routes of a traffic class (e.g. bulk into its own table)
+    pub fn with_class(mut self, class: TrafficClass) -> Self {
+        self.class = class;
+        self
+    }
+    
+    /// Push pending changes; returns the number of commands sent
+    pub async fn push(&self, manager: &CloudRouteManager) -> Result<usize, ConnectorError> {
+        let mut pushed = self.pushed.lock().await;
+        let desired = manager.forwarding_table_for(self.class);
+        let flow_hash = manager.flow_hash();
+        
+        let mut commands = Vec::new();
+        if pushed.flow_hash != Some(flow_hash) {
+            commands.push(flow_hash.vpp_command(self.table_id));
+        }
+        for (prefix, paths) in &desired {
+            let current = pushed.routes.get(prefix).map(Vec::as_slice).unwrap_or_default();
+            for path in paths.iter().filter(|p| !current.contains(p)) {
+                commands.push(self.route_command("add", prefix, path));
+            }
+            for path in current.iter().filter(|c| !paths.iter().any(|p| p.next_hop == c.next_hop)) {
+                commands.push(self.route_command("del", prefix, path));
+            }
+        }
+        for (prefix, paths) in pushed.routes.iter().filter(|(p, _)| !desired.contains_key(*p)) {
+            for path in paths {
+                commands.push(self.route_command("del", prefix, path));
+            }
+        }
+        
+        if commands.is_empty() {
+            return Ok(0);
+        }
+        self.executor.execute(&commands).await?;
+        tracing::info!("Pushed {} route commands to VPP table {}", commands.len(), self.table_id);
+        
+        pushed.routes = desired;
+        pushed.flow_hash = Some(flow_hash);
+        Ok(commands.len())
+    }
+    
+    /// Forget what VPP holds, e.g. after a VPP restart, so the next push is full
+    pub async fn reset(&self) {
+        *self.pushed.lock().await = PushedState::default();
+    }
+    
+    fn route_command(&self, op: &str, prefix: &IpNet, path: &FibPath) -> String {
+        if op == "add" {
+            format!("ip route add {} table {} via {} weight {}", prefix, self.table_id, path.next_hop, path.weight)
+        } else {
+            format!("ip route del {} table {} via {}", prefix, self.table_id, path.next_hop)
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! IPsec VPN fallback for dedicated connections
+//!
+//! When a Direct Connect / ExpressRoute / Interconnect goes down, brings up
+//! an IKEv2 tunnel to the provider's VPN gateway, injects the primary's
+//! prefixes over it at lower preference, and tears it down again once the
+//! primary has recovered.
+
+use crate::{
+    BgpConfig, BgpState, CloudConnection, CloudConnectorService, CloudProvider, CloudRoute,
+    ConnectionStatus, ConnectionType, ConnectorError, CreateConnectionRequest,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ipnet::Ipv4Net;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Route priority for fallback routes (primary routes use 100-200)
+pub const FALLBACK_ROUTE_PRIORITY: u32 = 50;
+
+/// BGP local preference for routes learned over the fallback tunnel
+pub const FALLBACK_LOCAL_PREF: u32 = 50;
+
+/// IKE/ESP proposals
+#[derive(Clone, Debug)]
+pub struct IkeProposals {
+    pub ike: String,
+    pub esp: String,
+    pub ike_lifetime_secs: u32,
+    pub esp_lifetime_secs: u32,
+}
+
+impl IkeProposals {
+    /// Proposals each provider's VPN gateway accepts by default
+    pub fn for_provider(provider: CloudProvider) -> Self {
+        let (ike, esp) = match provider {
+            CloudProvider::Aws => ("aes256-sha256-modp2048", "aes256-sha256-modp2048"),
+            CloudProvider::Azure => ("aes256-sha256-modp1024", "aes256-sha256"),
+            CloudProvider::Gcp => ("aes256gcm16-prfsha256-modp2048", "aes256gcm16-modp2048"),
+            CloudProvider::Oracle | CloudProvider::Alibaba => ("aes256-sha384-modp1536", "aes256-sha256-modp1536"),
+        };
+        Self {
+            ike: ike.to_string(),
+            esp: esp.to_string(),
+            ike_lifetime_secs: 28_800,
+            esp_lifetime_secs: 3_600,
+        }
+    }
+}
+
+/// Fallback settings for one dedicated connection
+#[derive(Clone, Debug)]
+pub struct VpnFallbackConfig {
+    pub primary_id: Uuid,
+    /// Provider VPN gateway public address
+    pub gateway_ip: IpAddr,
+    /// PoP public address the tunnel originates from
+    pub local_ip: IpAddr,
+    pub pre_shared_key: String,
+    /// /30 inside the tunnel; first host is ours, second the cloud's
+    pub inside_cidr: Ipv4Net,
+    /// Cloud-side ASN on the VPN gateway
+    pub cloud_asn: u32,
+    /// XFRM interface ID for route-based IPsec
+    pub if_id: u32,
+    pub proposals: IkeProposals,
+    pub bandwidth_mbps: u32,
+}
+
+impl VpnFallbackConfig {
+    pub fn new(
+        primary: &CloudConnection,
+        gateway_ip: IpAddr,
+        local_ip: IpAddr,
+        pre_shared_key: impl Into<String>,
+        inside_cidr: Ipv4Net,
+    ) -> Self {
+        Self {
+            primary_id: primary.id,
+            gateway_ip,
+            local_ip,
+            pre_shared_key: pre_shared_key.into(),
+            inside_cidr,
+            cloud_asn: primary.bgp_config.cloud_asn,
+            if_id: 100 + primary.vlan_id as u32,
+            proposals: IkeProposals::for_provider(primary.cloud_provider),
+            // Provider VPN gateways cap a tunnel at ~1.25 Gbps
+            bandwidth_mbps: primary.bandwidth_mbps.min(1_250),
+        }
+    }
+    
+    pub fn with_proposals(mut self, proposals: IkeProposals) -> Self {
+        self.proposals = proposals;
+        self
+    }
+    
+    pub fn with_if_id(mut self, if_id: u32) -> Self {
+        self.if_id = if_id;
+        self
+    }
+    
+    fn validate(&self) -> Result<(IpAddr, IpAddr), ConnectorError> {
+        if self.inside_cidr.prefix_len() != 30 {
+            return Err(ConnectorError::ValidationError(format!(
+                "Tunnel inside CIDR {} must be a /30", self.inside_cidr
+            )));
+        }
+        if self.pre_shared_key.len() < 8 {
+            return Err(ConnectorError::ValidationError("Pre-shared key must be at least 8 characters".to_string()));
+        }
+        let mut hosts = self.inside_cidr.hosts();
+        match (hosts.next(), hosts.next()) {
+            (Some(ours), Some(cloud)) => Ok((IpAddr::V4(ours), IpAddr::V4(cloud))),
+            _ => Err(ConnectorError::ValidationError(format!("No host addresses in {}", self.inside_cidr))),
+        }
+    }
+}
+
+/// A provisioned fallback tunnel
+#[derive(Clone, Debug)]
+pub struct FallbackTunnel {
+    pub name: String,
+    pub primary_id: Uuid,
+    /// The VpnTunnel connection created for the fallback
+    pub connection_id: Uuid,
+    pub swanctl_config: String,
+    pub bird_config: String,
+    pub injected_routes: Vec<CloudRoute>,
+    pub activated_at: DateTime<Utc>,
+}
+
+/// Generate strongSwan swanctl.conf for a route-based IKEv2 tunnel
+pub fn generate_swanctl_config(name: &str, config: &VpnFallbackConfig) -> String {
+    format!(r#"
+connections {{
+    {name} {{
+        version = 2
+        local_addrs = {local}
+        remote_addrs = {remote}
+        proposals = {ike}
+        rekey_time = {ike_lifetime}s
+        dpd_delay = 10s
+        if_id_in = {if_id}
+        if_id_out = {if_id}
+        local {{
+            auth = psk
+            id = {local}
+        }}
+        remote {{
+            auth = psk
+            id = {remote}
+        }}
+        children {{
+            {name} {{
+                local_ts = 0.0.0.0/0
+                remote_ts = 0.0.0.0/0
+                esp_proposals = {esp}
+                rekey_time = {esp_lifetime}s
+                start_action = start
+                dpd_action = restart
+                close_action = start
+            }}
+        }}
+    }}
+}}
+
+secrets {{
+    ike-{name} {{
+        id-local = {local}
+        id-remote = {remote}
+        secret = "{psk}"
+    }}
+}}
+"#,
+        name = name,
+        local = config.local_ip,
+        remote = config.gateway_ip,
+        ike = config.proposals.ike,
+        esp = config.proposals.esp,
+        ike_lifetime = config.proposals.ike_lifetime_secs,
+        esp_lifetime = config.proposals.esp_lifetime_secs,
+        if_id = config.if_id,
+        psk = config.pre_shared_key.replace('"', "\\\""),
+    )
+}
+
+/// Installs and removes IPsec tunnels on the PoP
+#[async_trait]
+pub trait IpsecBackend: Send + Sync {
+    async fn install(&self, tunnel: &FallbackTunnel) -> Result<(), ConnectorError>;
+    async fn remove(&self, tunnel: &FallbackTunnel) -> Result<(), ConnectorError>;
+}
+
+/// strongSwan backend driven through `swanctl`
+pub struct SwanctlBackend {
+    binary: String,
+    conf_dir: PathBuf,
+}
+
+impl SwanctlBackend {
+    pub fn new() -> Self {
+        Self {
+            binary: "swanctl".to_string(),
+            conf_dir: PathBuf::from("/etc/swanctl/conf.d"),
+        }
+    }
+    
+    pub fn with_binary(mut self, binary: impl Into<String>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+    
+    pub fn with_conf_dir(mut self, conf_dir: impl Into<PathBuf>) -> Self {
+        self.conf_dir = conf_dir.into();
+        self
+    }
+    
+    fn conf_path(&self, tunnel: &FallbackTunnel) -> PathBuf {
+        self.conf_dir.join(format!("{}.conf", tunnel.name))
+    }
+    
+    async fn swanctl(&self, args: &[&str]) -> Result<(), ConnectorError> {
+        let output = tokio::process::Command::new(&self.binary)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| ConnectorError::ProvisioningError(format!("{}: {}", self.binary, e)))?;
+        if !output.status.success() {
+            return Err(ConnectorError::ProvisioningError(format!(
+                "swanctl {}: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SwanctlBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl IpsecBackend for SwanctlBackend {
+    async fn install(&self, tunnel: &FallbackTunnel) -> Result<(), ConnectorError> {
+        tokio::fs::write(self.conf_path(tunnel), &tunnel.swanctl_config).await
+            .map_err(|e| ConnectorError::ProvisioningError(format!("writing swanctl config: {}", e)))?;
+        self.swanctl(&["--load-all"]).await?;
+        self.swanctl(&["--initiate", "--child", &tunnel.name]).await
+    }
+    
+    async fn remove(&self, tunnel: &FallbackTunnel) -> Result<(), ConnectorError> {
+        // Terminating an already-gone SA is not an error worth failing teardown on
+        if let Err(e) = self.swanctl(&["--terminate", "--ike", &tunnel.name]).await {
+            tracing::debug!("Terminating {}: {}", tunnel.name, e);
+        }
+        match tokio::fs::remove_file(self.conf_path(tunnel)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(ConnectorError::ProvisioningError(format!("removing swanctl config: {}", e))),
+        }
+        self.swanctl(&["--load-all"]).await
+    }
+}
+
+/// What a reconcile pass did
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FallbackAction {
+    Activated { primary_id: Uuid, connection_id: Uuid },
+    Deactivated { primary_id: Uuid, connection_id: Uuid },
+}
+
+/// Brings VPN fallbacks up and down with their primary connections
+pub struct VpnFallbackManager {
+    backend: Arc<dyn IpsecBackend>,
+    configs: dashmap::DashMap<Uuid, VpnFallbackConfig>,
+    active: dashmap::DashMap<Uuid, FallbackTunnel>,
+}
+
+impl VpnFallbackManager {
+    pub fn new(backend: Arc<dyn IpsecBackend>) -> Self {
+        Self {
+            backend,
+            configs: dashmap::DashMap::new(),
+            active: dashmap::DashMap::new(),
+        }
+    }
+    
+    /// Enable fallback for a dedicated connection
+    pub fn register(&self, primary: &CloudConnection, config: VpnFallbackConfig) -> Result<(), ConnectorError> {
+        if matches!(primary.connection_type, ConnectionType::VpnTunnel { .. }) {
+            return Err(ConnectorError::ValidationError(format!("{} is already a VPN tunnel", primary.name)));
+        }
+        config.validate()?;
+        self.configs.insert(primary.id, config);
+        Ok(())
+    }
+    
+    /// Disable fallback for a connection (an active tunnel stays until reconciled)
+    pub fn unregister(&self, primary_id: &Uuid) {
+        self.configs.remove(primary_id);
+    }
+    
+    /// Active fallback tunnel for a primary
+    pub fn active_tunnel(&self, primary_id: &Uuid) -> Option<FallbackTunnel> {
+        self.active.get(primary_id).map(|t| t.clone())
+    }
+    
+    /// Bring fallbacks in line with primary health
+    ///
+    /// A primary that is Down, or Degraded with its BGP session gone, gets a
+    /// tunnel; one that is Active with BGP established again loses it.
+    pub async fn reconcile(&self, service: &CloudConnectorService) -> Result<Vec<FallbackAction>, ConnectorError> {
+        let primaries: Vec<Uuid> = self.configs.iter().map(|c| *c.key())
+            .chain(self.active.iter().map(|t| *t.key()))
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+        
+        let mut actions = Vec::new();
+        for primary_id in primaries {
+            let primary = service.get_connection(&primary_id);
+            let down = primary.as_ref().is_some_and(|p| {
+                p.status == ConnectionStatus::Down
+                    || (p.status == ConnectionStatus::Degraded && p.health.bgp_state != BgpState::Established)
+            });
+            let recovered = primary.as_ref().is_none_or(|p| {
+                p.status == ConnectionStatus::Active && p.health.bgp_state == BgpState::Established
+            });
+            let registered = self.configs.contains_key(&primary_id);
+            
+            if down && registered && !self.active.contains_key(&primary_id) {
+                if let Some(primary) = &primary {
+                    let tunnel = self.activate(service, primary).await?;
+                    actions.push(FallbackAction::Activated { primary_id, connection_id: tunnel.connection_id });
+                }
+            } else if (recovered || !registered) && self.active.contains_key(&primary_id) {
+                let tunnel = self.deactivate(service, &primary_id).await?;
+                actions.push(FallbackAction::Deactivated { primary_id, connection_id: tunnel.connection_id });
+            }
+        }
+        Ok(actions)
+    }
+    
+    async fn activate(&self, service: &CloudConnectorService, primary: &CloudConnection) -> Result<FallbackTunnel, ConnectorError> {
+        let config = self.configs.get(&primary.id)
+            .map(|c| c.clone())
+            .ok_or(ConnectorError::ConnectionNotFound(primary.id))?;
+        let (our_ip, cloud_ip) = config.validate()?;
+        
+        let vpn = service.create_connection(primary.tenant_id, CreateConnectionRequest {
+            name: format!("{}-vpn-fallback", primary.name),
+            cloud_provider: primary.cloud_provider,
+            connection_type: ConnectionType::VpnTunnel { gateway_ip: config.gateway_ip },
+            bandwidth_mbps: config.bandwidth_mbps,
+            pop_location: primary.pop_location.clone(),
+            cloud_region: primary.cloud_region.clone(),
+            bgp_config: BgpConfig {
+                our_asn: primary.bgp_config.our_asn,
+                cloud_asn: config.cloud_asn,
+                our_ip,
+                cloud_ip,
+                md5_auth: None,
+                advertised_prefixes: primary.bgp_config.advertised_prefixes.clone(),
+                received_prefixes: Vec::new(),
+                local_preference: FALLBACK_LOCAL_PREF,
+                med: primary.bgp_config.med,
+            },
+            vlan_id: 0,
+        }).await?;
+        service.update_status(&vpn.id, ConnectionStatus::Provisioning)?;
+        
+        let name = format!("fallback_{}", &primary.id.simple().to_string()[..8]);
+        let mut tunnel = FallbackTunnel {
+            swanctl_config: generate_swanctl_config(&name, &config),
+            bird_config: service.generate_bird_config(&vpn),
+            name,
+            primary_id: primary.id,
+            connection_id: vpn.id,
+            injected_routes: Vec::new(),
+            activated_at: Utc::now(),
+        };
+        
+        if let Err(e) = self.backend.install(&tunnel).await {
+            tracing::error!("VPN fallback for {} failed to come up: {}", primary.name, e);
+            service.update_status(&vpn.id, ConnectionStatus::Down)?;
+            service.delete_connection(&vpn.id).await?;
+            return Err(e);
+        }
+        service.update_status(&vpn.id, ConnectionStatus::Active)?;
+        
+        // Carry the primary's prefixes over the tunnel, below any primary route
+        let prefixes: Vec<_> = service.route_manager().get_all_routes()
+            .into_iter()
+            .filter(|(_, routes)| routes.iter().any(|r| r.connection_id == primary.id))
+            .map(|(prefix, _)| prefix)
+            .collect();
+        for prefix in prefixes {
+            let route = CloudRoute {
+                prefix,
+                next_hop: cloud_ip,
+                connection_id: vpn.id,
+                priority: FALLBACK_ROUTE_PRIORITY,
+                weight: 1,
+                active: true,
+            };
+            service.add_route(&vpn.id, route.clone()).await?;
+            tunnel.injected_routes.push(route);
+        }
+        service.route_manager().withdraw_connection(&primary.id);
+        
+        tracing::warn!(
+            "{} is down; VPN fallback {} up to {} with {} routes",
+            primary.name, tunnel.name, config.gateway_ip, tunnel.injected_routes.len()
+        );
+        self.active.insert(primary.id, tunnel.clone());
+        Ok(tunnel)
+    }
+    
+    async fn deactivate(&self, service: &CloudConnectorService, primary_id: &Uuid) -> Result<FallbackTunnel, ConnectorError> {
+        let Some((_, tunnel)) = self.active.remove(primary_id) else {
+            return Err(ConnectorError::ConnectionNotFound(*primary_id));
+        };
+        
+        // Primary routes first, so traffic never lacks a path
+        service.route_manager().restore_connection(primary_id);
+        for route in &tunnel.injected_routes {
+            service.route_manager().remove_route(&route.prefix, &tunnel.connection_id);
+        }
+        
+        if let Err(e) = self.backend.remove(&tunnel).await {
+            // Routes are already off the tunnel; keep tracking it for retry
+            self.active.insert(*primary_id, tunnel.clone());
+            return Err(e);
+        }
+        if service.get_connection(&tunnel.connection_id).is_some() {
+            service.delete_connection(&tunnel.connection_id).await?;
+        }
+        
+        tracing::info!("Primary {} recovered; VPN fallback {} torn down", primary_id, tunnel.name);
+        Ok(tunnel)
+    }
+}
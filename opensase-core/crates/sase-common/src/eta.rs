@@ -0,0 +1,88 @@
+//! Encrypted Traffic Analysis (ETA) records
+//!
+//! Per-flow features of TLS traffic that cannot be decrypted, exported by the
+//! dataplane ETA extractor and scored by the inference engine: the sequence
+//! of packet lengths and times (SPLT) plus handshake metadata (JA4, SNI,
+//! ALPN, certificate validity).
+
+use serde::{Deserialize, Serialize};
+
+/// Packet direction relative to the flow initiator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Initiator → responder
+    Outbound,
+    /// Responder → initiator
+    Inbound,
+}
+
+/// One entry of the SPLT sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PacketSample {
+    /// Direction
+    pub direction: Direction,
+    /// L4 payload length
+    pub payload_len: u16,
+    /// Time since the previous packet of the flow (microseconds)
+    pub iat_us: u32,
+}
+
+/// Server certificate validity (TLS ≤1.2; encrypted in TLS 1.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    /// notBefore (unix seconds)
+    pub not_before: i64,
+    /// notAfter (unix seconds)
+    pub not_after: i64,
+    /// Issuer equals subject (unknown if the certificate was truncated)
+    pub self_signed: Option<bool>,
+}
+
+impl CertificateInfo {
+    /// Certificate age at `now` (seconds)
+    pub fn age_secs(&self, now: i64) -> i64 {
+        now - self.not_before
+    }
+}
+
+/// Encrypted flow record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtaRecord {
+    /// Initiator address
+    pub src_ip: u32,
+    /// Responder address
+    pub dst_ip: u32,
+    /// Initiator port
+    pub src_port: u16,
+    /// Responder port
+    pub dst_port: u16,
+    /// IP protocol
+    pub protocol: u8,
+    /// First packet (unix microseconds)
+    pub start_us: u64,
+    /// Flow duration so far (microseconds)
+    pub duration_us: u64,
+    /// Initiator → responder packets
+    pub packets_out: u64,
+    /// Responder → initiator packets
+    pub packets_in: u64,
+    /// Initiator → responder payload bytes
+    pub bytes_out: u64,
+    /// Responder → initiator payload bytes
+    pub bytes_in: u64,
+    /// Sequence of packet lengths and times
+    pub splt: Vec<PacketSample>,
+    /// JA4 fingerprint
+    pub ja4: Option<String>,
+    /// Server name indication
+    pub sni: Option<String>,
+    /// First offered ALPN protocol
+    pub alpn: Option<String>,
+    /// Negotiated/offered TLS version
+    pub tls_version: Option<u16>,
+    /// Server certificate validity
+    pub certificate: Option<CertificateInfo>,
+    /// Final record for an ended flow
+    pub complete: bool,
+}
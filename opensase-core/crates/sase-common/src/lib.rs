@@ -35,6 +35,8 @@ pub mod metrics;
 pub mod error;
 pub mod domain;
 pub mod acl;
+pub mod smtp;
+pub mod eta;
 
 pub use policy::*;
 pub use flow::*;
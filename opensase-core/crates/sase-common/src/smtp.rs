@@ -0,0 +1,188 @@
+//! SMTP submission for platform notifications
+//!
+//! Minimal client for relays that accept mail from platform services without
+//! authentication (an internal MTA or the email security gateway). Sends one
+//! plain-text message per connection.
+
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+/// SMTP delivery error
+#[derive(Error, Debug)]
+pub enum SmtpError {
+    /// Relay unreachable or the connection failed
+    #[error("{0}")]
+    Transport(String),
+    /// Relay answered with an unexpected or malformed reply
+    #[error("{0}")]
+    Rejected(String),
+}
+
+/// Unauthenticated SMTP relay
+#[derive(Debug, Clone)]
+pub struct SmtpRelay {
+    /// `host:port` of the relay
+    pub address: String,
+    /// Envelope and header sender
+    pub from: String,
+    /// Name sent in EHLO and used for Message-IDs
+    pub helo: String,
+}
+
+impl SmtpRelay {
+    /// Relay at `address` sending as `from`
+    pub fn new(address: &str, from: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            from: from.to_string(),
+            helo: "localhost.localdomain".to_string(),
+        }
+    }
+
+    /// Name to announce in EHLO
+    pub fn with_helo(mut self, helo: &str) -> Self {
+        self.helo = helo.to_string();
+        self
+    }
+
+    /// Deliver a plain-text message to one recipient
+    pub async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), SmtpError> {
+        let stream = TcpStream::connect(&self.address).await
+            .map_err(|e| SmtpError::Transport(format!("{}: {}", self.address, e)))?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        expect_reply(&mut reader, 220).await?;
+        command(&mut reader, &mut writer, &format!("EHLO {}", self.helo), 250).await?;
+        command(&mut reader, &mut writer, &format!("MAIL FROM:<{}>", self.from), 250).await?;
+        command(&mut reader, &mut writer, &format!("RCPT TO:<{}>", to), 250).await?;
+        command(&mut reader, &mut writer, "DATA", 354).await?;
+
+        let mut message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@{}>\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+            self.from,
+            to,
+            subject.replace(['\r', '\n'], " "),
+            chrono::Utc::now().to_rfc2822(),
+            uuid::Uuid::new_v4(),
+            self.helo,
+        );
+        for line in body.lines() {
+            // Dot-stuffing (RFC 5321 4.5.2)
+            if line.starts_with('.') {
+                message.push('.');
+            }
+            message.push_str(line);
+            message.push_str("\r\n");
+        }
+        message.push_str(".\r\n");
+        writer.write_all(message.as_bytes()).await.map_err(|e| SmtpError::Transport(e.to_string()))?;
+        expect_reply(&mut reader, 250).await?;
+
+        let _ = writer.write_all(b"QUIT\r\n").await;
+        Ok(())
+    }
+}
+
+async fn command(
+    reader: &mut BufReader<OwnedReadHalf>,
+    writer: &mut OwnedWriteHalf,
+    line: &str,
+    expected: u16,
+) -> Result<(), SmtpError> {
+    writer.write_all(format!("{}\r\n", line).as_bytes()).await
+        .map_err(|e| SmtpError::Transport(e.to_string()))?;
+    expect_reply(reader, expected).await
+}
+
+/// Read a possibly multi-line reply and check its code
+async fn expect_reply(reader: &mut BufReader<OwnedReadHalf>, expected: u16) -> Result<(), SmtpError> {
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).await.map_err(|e| SmtpError::Transport(e.to_string()))?;
+        if read == 0 {
+            return Err(SmtpError::Transport("relay closed the connection".to_string()));
+        }
+        let code: u16 = line.get(..3).and_then(|c| c.parse().ok())
+            .ok_or_else(|| SmtpError::Rejected(format!("malformed reply: {}", line.trim_end())))?;
+        // "250-..." continues, "250 ..." ends the reply
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        // Positive replies of the same class (e.g. 251) are fine
+        if code / 100 != expected / 100 {
+            return Err(SmtpError::Rejected(line.trim_end().to_string()));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Relay answering each command from `replies` in turn; returns what the client sent
+    async fn relay(replies: &'static [&'static str]) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let mut received = String::new();
+            let mut in_data = false;
+
+            writer.write_all(b"220 relay ready\r\n").await.unwrap();
+            let mut replies = replies.iter();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                    break;
+                }
+                received.push_str(&line);
+                if in_data && line != ".\r\n" {
+                    continue;
+                }
+                let Some(reply) = replies.next() else { break };
+                writer.write_all(reply.as_bytes()).await.unwrap();
+                in_data = reply.starts_with("354");
+            }
+            received
+        });
+        (address, handle)
+    }
+
+    #[tokio::test]
+    async fn test_send_dot_stuffs_body() {
+        let (address, handle) = relay(&[
+            "250-relay.example\r\n250-SIZE 1000000\r\n250 OK\r\n",
+            "250 OK\r\n",
+            "251 will forward\r\n",
+            "354 go ahead\r\n",
+            "250 queued\r\n",
+            "221 bye\r\n",
+        ]).await;
+
+        let relay = SmtpRelay::new(&address, "alerts@sase.example").with_helo("soc.example");
+        relay.send("oncall@corp.example", "Alert\r\nBcc: x", "line one\n.hidden").await.unwrap();
+
+        let received = handle.await.unwrap();
+        assert!(received.starts_with("EHLO soc.example\r\nMAIL FROM:<alerts@sase.example>\r\nRCPT TO:<oncall@corp.example>\r\nDATA\r\n"));
+        assert!(received.contains("Subject: Alert  Bcc: x\r\n"));
+        assert!(received.contains("\r\n\r\nline one\r\n..hidden\r\n.\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_send_reports_rejection() {
+        let (address, _) = relay(&["250 OK\r\n", "250 OK\r\n", "550 5.1.1 no such user\r\n"]).await;
+
+        let err = SmtpRelay::new(&address, "alerts@sase.example")
+            .send("nobody@corp.example", "Alert", "body")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SmtpError::Rejected(ref reply) if reply == "550 5.1.1 no such user"), "{:?}", err);
+    }
+}
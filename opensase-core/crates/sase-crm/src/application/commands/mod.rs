@@ -5,11 +5,16 @@
 use std::sync::Arc;
 use async_trait::async_trait;
 
-use crate::domain::aggregates::{Contact, Deal};
+use crate::domain::aggregates::{
+    Contact, Contract, ContractTerms, Deal, RelatedRecord, Task, TaskPriority,
+};
 use crate::domain::value_objects::{Email, EntityId, Money, Currency};
 use crate::domain::services::LeadScoringService;
-use crate::ports::outbound::{ContactRepository, DealRepository, EventPublisher, RepositoryError};
-use crate::ports::inbound::{ContactUseCases, DealUseCases, UseCaseError};
+use crate::ports::outbound::{
+    ContactRepository, ContractRepository, DealRepository, EventPublisher, RepositoryError,
+    TaskRepository,
+};
+use crate::ports::inbound::{ContactUseCases, ContractUseCases, DealUseCases, UseCaseError};
 use crate::application::dto::*;
 
 /// Contact application service
@@ -270,3 +275,175 @@ impl DealUseCases for DealService {
         Err(UseCaseError::NotFound("Not implemented".into()))
     }
 }
+
+/// Contract application service
+pub struct ContractService {
+    contract_repo: Arc<dyn ContractRepository>,
+    deal_repo: Arc<dyn DealRepository>,
+    task_repo: Arc<dyn TaskRepository>,
+    event_publisher: Arc<dyn EventPublisher>,
+}
+
+impl ContractService {
+    pub fn new(
+        contract_repo: Arc<dyn ContractRepository>,
+        deal_repo: Arc<dyn DealRepository>,
+        task_repo: Arc<dyn TaskRepository>,
+        event_publisher: Arc<dyn EventPublisher>,
+    ) -> Self {
+        Self {
+            contract_repo,
+            deal_repo,
+            task_repo,
+            event_publisher,
+        }
+    }
+    
+    async fn load(&self, contract_id: &EntityId) -> Result<Contract, UseCaseError> {
+        self.contract_repo.find_by_id(contract_id).await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?
+            .ok_or_else(|| UseCaseError::NotFound("Contract not found".into()))
+    }
+    
+    async fn store(&self, contract: &mut Contract) -> Result<(), UseCaseError> {
+        self.contract_repo.save(contract).await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?;
+        
+        let events = contract.take_events();
+        self.event_publisher.publish(events).await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl ContractUseCases for ContractService {
+    async fn create_contract(&self, command: CreateContractCommand) -> Result<Contract, UseCaseError> {
+        let deal_id = EntityId::from_string(&command.deal_id);
+        let deal = self.deal_repo.find_by_id(&deal_id).await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?
+            .ok_or_else(|| UseCaseError::NotFound("Deal not found".into()))?;
+        
+        let committed_spend = Money::new(
+            command.committed_spend,
+            Currency::from_code(&command.currency),
+        );
+        
+        let mut terms = ContractTerms::new(command.end_date, committed_spend);
+        terms.auto_renew = command.auto_renew;
+        terms.service_levels = command.service_levels;
+        terms.entitlements = command.entitlements;
+        
+        let mut contract = Contract::create(
+            command.name,
+            &deal,
+            command.start_date,
+            terms,
+            EntityId::from_string(&command.owner_id),
+        ).map_err(|e| UseCaseError::DomainError(e.to_string()))?;
+        
+        self.store(&mut contract).await?;
+        
+        Ok(contract)
+    }
+    
+    async fn activate_contract(&self, contract_id: &EntityId, subscription_id: &str) -> Result<Contract, UseCaseError> {
+        let existing = self.contract_repo.find_by_subscription(subscription_id).await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?;
+        if existing.is_some_and(|c| c.id() != contract_id) {
+            return Err(UseCaseError::ValidationError(
+                "Subscription is already linked to another contract".into(),
+            ));
+        }
+        
+        let mut contract = self.load(contract_id).await?;
+        
+        contract.activate(subscription_id)
+            .map_err(|e| UseCaseError::DomainError(e.to_string()))?;
+        
+        self.store(&mut contract).await?;
+        
+        Ok(contract)
+    }
+    
+    async fn amend_contract(&self, command: AmendContractCommand) -> Result<Contract, UseCaseError> {
+        let mut contract = self.load(&EntityId::from_string(&command.contract_id)).await?;
+        
+        contract.amend(
+            command.effective_date,
+            command.changes,
+            command.reason,
+            EntityId::from_string(&command.recorded_by),
+        ).map_err(|e| UseCaseError::DomainError(e.to_string()))?;
+        
+        self.store(&mut contract).await?;
+        
+        Ok(contract)
+    }
+    
+    async fn terminate_contract(&self, contract_id: &EntityId, reason: String) -> Result<Contract, UseCaseError> {
+        let mut contract = self.load(contract_id).await?;
+        
+        contract.terminate(reason)
+            .map_err(|e| UseCaseError::DomainError(e.to_string()))?;
+        
+        self.store(&mut contract).await?;
+        
+        Ok(contract)
+    }
+    
+    async fn get_contract(&self, id: &EntityId) -> Result<Option<Contract>, UseCaseError> {
+        self.contract_repo.find_by_id(id).await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))
+    }
+    
+    async fn get_terms_as_of(&self, contract_id: &EntityId, date: chrono::NaiveDate) -> Result<ContractTerms, UseCaseError> {
+        let contract = self.load(contract_id).await?;
+        Ok(contract.terms_as_of(date))
+    }
+    
+    async fn run_renewal_reminders(&self, today: chrono::NaiveDate) -> Result<Vec<Task>, UseCaseError> {
+        let contracts = self.contract_repo.find_active().await
+            .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?;
+        
+        let mut tasks = Vec::new();
+        for mut contract in contracts {
+            let Some(reminder) = contract.record_renewal_reminder(today) else {
+                continue;
+            };
+            
+            let mut task = Task::create(
+                format!("Renewal in {} days: {}", reminder.days_before, contract.name()),
+                RelatedRecord::Contract(contract.id().clone()),
+                contract.owner_id().clone(),
+                Some(reminder.follow_up_by()),
+            );
+            task.set_description(if reminder.auto_renew {
+                format!(
+                    "{} ends on {} and renews automatically. Confirm the renewal terms with the customer.",
+                    contract.name(),
+                    reminder.end_date,
+                )
+            } else {
+                format!(
+                    "{} ends on {}. Open a renewal deal and agree the new term before it lapses.",
+                    contract.name(),
+                    reminder.end_date,
+                )
+            });
+            if reminder.days_before <= 30 {
+                task.set_priority(TaskPriority::High);
+            }
+            
+            self.task_repo.save(&task).await
+                .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?;
+            let events = task.take_events();
+            self.event_publisher.publish(events).await
+                .map_err(|e| UseCaseError::RepositoryError(e.to_string()))?;
+            
+            self.store(&mut contract).await?;
+            tasks.push(task);
+        }
+        
+        Ok(tasks)
+    }
+}
@@ -5,6 +5,7 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use crate::domain::aggregates::{Entitlement, ServiceLevel, TermChange};
 use crate::domain::value_objects::EntityId;
 
 // =============================================================================
@@ -58,6 +59,33 @@ pub struct MoveDealStageCommand {
     pub probability: u8,
 }
 
+// =============================================================================
+// Contract Commands
+// =============================================================================
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateContractCommand {
+    pub deal_id: String,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub auto_renew: bool,
+    pub committed_spend: Decimal,
+    pub currency: String,
+    pub service_levels: Vec<ServiceLevel>,
+    pub entitlements: Vec<Entitlement>,
+    pub owner_id: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AmendContractCommand {
+    pub contract_id: String,
+    pub effective_date: NaiveDate,
+    pub changes: Vec<TermChange>,
+    pub reason: String,
+    pub recorded_by: String,
+}
+
 // =============================================================================
 // Views (Read Models)
 // =============================================================================
@@ -6,5 +6,5 @@ pub mod commands;
 pub mod queries;
 pub mod dto;
 
-pub use commands::{ContactService, DealService};
+pub use commands::{ContactService, ContractService, DealService};
 pub use dto::*;
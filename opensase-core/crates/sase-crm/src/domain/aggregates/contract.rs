@@ -0,0 +1,706 @@
+//! Contract Aggregate
+//!
+//! Signed agreement produced by a won deal: term, committed spend, SLAs and
+//! entitlements, with effective-dated amendments and renewal tracking.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::aggregates::deal::Deal;
+use crate::domain::value_objects::{EntityId, Money};
+use crate::domain::events::{DomainEvent, ContractEvent};
+
+/// Days before the end of term at which renewal reminders fire
+pub const RENEWAL_REMINDER_DAYS: [u32; 3] = [90, 60, 30];
+
+/// Contract aggregate root
+#[derive(Clone, Debug)]
+pub struct Contract {
+    id: EntityId,
+    name: String,
+    deal_id: EntityId,
+    account_id: Option<EntityId>,
+    subscription_id: Option<String>,
+    owner_id: EntityId,
+    start_date: NaiveDate,
+    original_terms: ContractTerms,
+    amendments: Vec<Amendment>,
+    status: ContractStatus,
+    renewal_reminders: Vec<RenewalReminder>,
+    renewal_contract_id: Option<EntityId>,
+    termination_reason: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    events: Vec<DomainEvent>,
+}
+
+impl Contract {
+    /// Create a draft contract from a won deal
+    pub fn create(
+        name: impl Into<String>,
+        deal: &Deal,
+        start_date: NaiveDate,
+        terms: ContractTerms,
+        owner_id: EntityId,
+    ) -> Result<Self, ContractError> {
+        if !deal.is_won() {
+            return Err(ContractError::DealNotWon);
+        }
+        
+        if terms.end_date <= start_date {
+            return Err(ContractError::InvalidTerm);
+        }
+        
+        let now = Utc::now();
+        let id = EntityId::new();
+        
+        let mut contract = Self {
+            id: id.clone(),
+            name: name.into(),
+            deal_id: deal.id().clone(),
+            account_id: deal.account_id().cloned(),
+            subscription_id: None,
+            owner_id: owner_id.clone(),
+            start_date,
+            original_terms: terms,
+            amendments: vec![],
+            status: ContractStatus::Draft,
+            renewal_reminders: vec![],
+            renewal_contract_id: None,
+            termination_reason: None,
+            created_at: now,
+            updated_at: now,
+            events: vec![],
+        };
+        
+        contract.raise_event(DomainEvent::Contract(ContractEvent::Created {
+            contract_id: id,
+            deal_id: contract.deal_id.clone(),
+            committed_spend: contract.original_terms.committed_spend.amount(),
+            start_date,
+            end_date: contract.original_terms.end_date,
+            owner_id,
+            created_at: now,
+        }));
+        
+        Ok(contract)
+    }
+    
+    // =========================================================================
+    // Getters
+    // =========================================================================
+    
+    pub fn id(&self) -> &EntityId { &self.id }
+    pub fn name(&self) -> &str { &self.name }
+    pub fn deal_id(&self) -> &EntityId { &self.deal_id }
+    pub fn account_id(&self) -> Option<&EntityId> { self.account_id.as_ref() }
+    pub fn subscription_id(&self) -> Option<&str> { self.subscription_id.as_deref() }
+    pub fn owner_id(&self) -> &EntityId { &self.owner_id }
+    pub fn start_date(&self) -> NaiveDate { self.start_date }
+    pub fn original_terms(&self) -> &ContractTerms { &self.original_terms }
+    pub fn amendments(&self) -> &[Amendment] { &self.amendments }
+    pub fn status(&self) -> &ContractStatus { &self.status }
+    pub fn is_active(&self) -> bool { self.status == ContractStatus::Active }
+    pub fn renewal_reminders(&self) -> &[RenewalReminder] { &self.renewal_reminders }
+    pub fn renewal_contract_id(&self) -> Option<&EntityId> { self.renewal_contract_id.as_ref() }
+    pub fn termination_reason(&self) -> Option<&str> { self.termination_reason.as_deref() }
+    pub fn created_at(&self) -> DateTime<Utc> { self.created_at }
+    pub fn updated_at(&self) -> DateTime<Utc> { self.updated_at }
+    
+    /// Terms in force on `date`: the original terms with every amendment
+    /// effective on or before that date applied in order
+    pub fn terms_as_of(&self, date: NaiveDate) -> ContractTerms {
+        let mut terms = self.original_terms.clone();
+        for amendment in self.amendments.iter().filter(|a| a.effective_date <= date) {
+            for change in &amendment.changes {
+                terms.apply(change);
+            }
+        }
+        terms
+    }
+    
+    /// Terms in force today
+    pub fn current_terms(&self) -> ContractTerms {
+        self.terms_as_of(Utc::now().date_naive())
+    }
+    
+    /// Days from `today` until the end of the term in force on `today`
+    pub fn days_until_end(&self, today: NaiveDate) -> i64 {
+        (self.terms_as_of(today).end_date - today).num_days()
+    }
+    
+    // =========================================================================
+    // Business Operations
+    // =========================================================================
+    
+    /// Activate once the billing subscription has been provisioned
+    pub fn activate(&mut self, subscription_id: impl Into<String>) -> Result<(), ContractError> {
+        if self.status != ContractStatus::Draft {
+            return Err(ContractError::NotDraft);
+        }
+        
+        let subscription_id = subscription_id.into();
+        let now = Utc::now();
+        
+        self.subscription_id = Some(subscription_id.clone());
+        self.status = ContractStatus::Active;
+        self.touch();
+        
+        self.raise_event(DomainEvent::Contract(ContractEvent::Activated {
+            contract_id: self.id.clone(),
+            subscription_id,
+            activated_at: now,
+        }));
+        
+        Ok(())
+    }
+    
+    /// Record an amendment taking effect on `effective_date`
+    pub fn amend(
+        &mut self,
+        effective_date: NaiveDate,
+        changes: Vec<TermChange>,
+        reason: impl Into<String>,
+        recorded_by: EntityId,
+    ) -> Result<&Amendment, ContractError> {
+        if !matches!(self.status, ContractStatus::Draft | ContractStatus::Active) {
+            return Err(ContractError::NotAmendable);
+        }
+        
+        if changes.is_empty() {
+            return Err(ContractError::EmptyAmendment);
+        }
+        
+        if effective_date < self.start_date
+            || effective_date > self.terms_as_of(effective_date).end_date
+        {
+            return Err(ContractError::EffectiveDateOutsideTerm);
+        }
+        
+        // Spend stays in the contract currency and the term cannot end
+        // before it starts or before the amendment takes effect
+        let currency = self.original_terms.committed_spend.currency().clone();
+        for change in &changes {
+            match change {
+                TermChange::CommittedSpend(spend) if spend.currency() != &currency => {
+                    return Err(ContractError::CurrencyMismatch);
+                }
+                TermChange::EndDate(end) if *end <= self.start_date || *end < effective_date => {
+                    return Err(ContractError::InvalidTerm);
+                }
+                _ => {}
+            }
+        }
+        
+        let now = Utc::now();
+        let sequence = self.amendments.len() as u32 + 1;
+        let amendment = Amendment {
+            id: EntityId::new(),
+            sequence,
+            effective_date,
+            changes,
+            reason: reason.into(),
+            recorded_by,
+            recorded_at: now,
+        };
+        
+        // Keep amendments in application order; same-day amendments apply in
+        // the order they were recorded
+        let position = self.amendments
+            .iter()
+            .position(|a| a.effective_date > effective_date)
+            .unwrap_or(self.amendments.len());
+        self.amendments.insert(position, amendment);
+        self.touch();
+        
+        self.raise_event(DomainEvent::Contract(ContractEvent::Amended {
+            contract_id: self.id.clone(),
+            sequence,
+            effective_date,
+            recorded_at: now,
+        }));
+        
+        Ok(&self.amendments[position])
+    }
+    
+    /// Record the renewal reminder due on `today`, if any
+    ///
+    /// Only the nearest threshold fires when several are crossed at once, and
+    /// reminders re-arm when the end date is moved by an amendment.
+    pub fn record_renewal_reminder(&mut self, today: NaiveDate) -> Option<RenewalReminder> {
+        if !self.is_active() {
+            return None;
+        }
+        
+        let terms = self.terms_as_of(today);
+        let days_left = (terms.end_date - today).num_days();
+        if days_left < 0 {
+            return None;
+        }
+        
+        let days_before = RENEWAL_REMINDER_DAYS
+            .iter()
+            .copied()
+            .filter(|d| days_left <= i64::from(*d))
+            .min()?;
+        
+        let already_sent = self.renewal_reminders.iter().any(|r| {
+            r.end_date == terms.end_date && r.days_before <= days_before
+        });
+        if already_sent {
+            return None;
+        }
+        
+        let reminder = RenewalReminder {
+            days_before,
+            end_date: terms.end_date,
+            auto_renew: terms.auto_renew,
+            sent_on: today,
+        };
+        self.renewal_reminders.push(reminder.clone());
+        self.touch();
+        
+        self.raise_event(DomainEvent::Contract(ContractEvent::RenewalReminderDue {
+            contract_id: self.id.clone(),
+            days_before,
+            end_date: terms.end_date,
+            owner_id: self.owner_id.clone(),
+        }));
+        
+        Some(reminder)
+    }
+    
+    /// Mark as renewed by a follow-on contract
+    pub fn mark_renewed(&mut self, renewal_contract_id: EntityId) -> Result<(), ContractError> {
+        if !self.is_active() {
+            return Err(ContractError::NotActive);
+        }
+        
+        self.status = ContractStatus::Renewed;
+        self.renewal_contract_id = Some(renewal_contract_id.clone());
+        self.touch();
+        
+        self.raise_event(DomainEvent::Contract(ContractEvent::Renewed {
+            contract_id: self.id.clone(),
+            renewal_contract_id,
+        }));
+        
+        Ok(())
+    }
+    
+    /// Expire a contract whose term has ended
+    pub fn expire(&mut self, today: NaiveDate) -> Result<(), ContractError> {
+        if !self.is_active() {
+            return Err(ContractError::NotActive);
+        }
+        
+        if today <= self.terms_as_of(today).end_date {
+            return Err(ContractError::TermNotEnded);
+        }
+        
+        self.status = ContractStatus::Expired;
+        self.touch();
+        
+        self.raise_event(DomainEvent::Contract(ContractEvent::Expired {
+            contract_id: self.id.clone(),
+            expired_on: today,
+        }));
+        
+        Ok(())
+    }
+    
+    /// Terminate early
+    pub fn terminate(&mut self, reason: impl Into<String>) -> Result<(), ContractError> {
+        if !matches!(self.status, ContractStatus::Draft | ContractStatus::Active) {
+            return Err(ContractError::NotAmendable);
+        }
+        
+        let now = Utc::now();
+        let reason = reason.into();
+        
+        self.status = ContractStatus::Terminated;
+        self.termination_reason = Some(reason.clone());
+        self.touch();
+        
+        self.raise_event(DomainEvent::Contract(ContractEvent::Terminated {
+            contract_id: self.id.clone(),
+            reason,
+            terminated_at: now,
+        }));
+        
+        Ok(())
+    }
+    
+    // =========================================================================
+    // Private
+    // =========================================================================
+    
+    pub fn take_events(&mut self) -> Vec<DomainEvent> {
+        std::mem::take(&mut self.events)
+    }
+    
+    fn raise_event(&mut self, event: DomainEvent) {
+        self.events.push(event);
+    }
+    
+    fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+}
+
+// =============================================================================
+// Supporting Types
+// =============================================================================
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContractStatus {
+    Draft,
+    Active,
+    Expired,
+    Terminated,
+    Renewed,
+}
+
+/// Commercial terms of a contract
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContractTerms {
+    pub end_date: NaiveDate,
+    pub auto_renew: bool,
+    /// Committed spend over the term
+    pub committed_spend: Money,
+    pub service_levels: Vec<ServiceLevel>,
+    pub entitlements: Vec<Entitlement>,
+}
+
+impl ContractTerms {
+    pub fn new(end_date: NaiveDate, committed_spend: Money) -> Self {
+        Self {
+            end_date,
+            auto_renew: false,
+            committed_spend,
+            service_levels: vec![],
+            entitlements: vec![],
+        }
+    }
+    
+    /// Term length in days from `start_date`
+    pub fn term_days(&self, start_date: NaiveDate) -> i64 {
+        (self.end_date - start_date).num_days()
+    }
+    
+    pub fn entitlement(&self, sku: &str) -> Option<&Entitlement> {
+        self.entitlements.iter().find(|e| e.sku == sku)
+    }
+    
+    pub fn service_level(&self, metric: &SlaMetric) -> Option<&ServiceLevel> {
+        self.service_levels.iter().find(|s| &s.metric == metric)
+    }
+    
+    fn apply(&mut self, change: &TermChange) {
+        match change {
+            TermChange::EndDate(date) => self.end_date = *date,
+            TermChange::AutoRenew(auto_renew) => self.auto_renew = *auto_renew,
+            TermChange::CommittedSpend(spend) => self.committed_spend = spend.clone(),
+            TermChange::SetEntitlement(entitlement) => {
+                self.entitlements.retain(|e| e.sku != entitlement.sku);
+                self.entitlements.push(entitlement.clone());
+            }
+            TermChange::RemoveEntitlement { sku } => {
+                self.entitlements.retain(|e| &e.sku != sku);
+            }
+            TermChange::SetServiceLevel(level) => {
+                self.service_levels.retain(|s| s.metric != level.metric);
+                self.service_levels.push(level.clone());
+            }
+            TermChange::RemoveServiceLevel { metric } => {
+                self.service_levels.retain(|s| &s.metric != metric);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlaMetric {
+    /// Monthly uptime percentage
+    Availability,
+    /// Round-trip latency in milliseconds
+    Latency,
+    /// Support first-response time in minutes
+    SupportResponse,
+    /// Incident resolution time in hours
+    IncidentResolution,
+}
+
+/// Service level commitment
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServiceLevel {
+    pub metric: SlaMetric,
+    pub target: Decimal,
+    /// Service credit owed when the target is missed, as % of monthly fees
+    pub credit_percent: Decimal,
+}
+
+/// Product or capacity the customer is entitled to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entitlement {
+    pub sku: String,
+    pub description: String,
+    pub quantity: u32,
+}
+
+/// Single change carried by an amendment
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TermChange {
+    EndDate(NaiveDate),
+    AutoRenew(bool),
+    CommittedSpend(Money),
+    /// Add an entitlement or replace the one with the same SKU
+    SetEntitlement(Entitlement),
+    RemoveEntitlement { sku: String },
+    /// Add a service level or replace the one for the same metric
+    SetServiceLevel(ServiceLevel),
+    RemoveServiceLevel { metric: SlaMetric },
+}
+
+/// Effective-dated amendment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Amendment {
+    pub id: EntityId,
+    /// Order in which the amendment was recorded (1-based)
+    pub sequence: u32,
+    pub effective_date: NaiveDate,
+    pub changes: Vec<TermChange>,
+    pub reason: String,
+    pub recorded_by: EntityId,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Renewal reminder that has fired
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenewalReminder {
+    pub days_before: u32,
+    /// End date the reminder was raised against
+    pub end_date: NaiveDate,
+    pub auto_renew: bool,
+    pub sent_on: NaiveDate,
+}
+
+impl RenewalReminder {
+    /// Follow-up is due by the next reminder, or by the end date for the last one
+    pub fn follow_up_by(&self) -> NaiveDate {
+        let next = RENEWAL_REMINDER_DAYS
+            .iter()
+            .copied()
+            .filter(|d| *d < self.days_before)
+            .max()
+            .unwrap_or(0);
+        self.end_date - Duration::days(i64::from(next))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractError {
+    DealNotWon,
+    InvalidTerm,
+    NotDraft,
+    NotActive,
+    NotAmendable,
+    EmptyAmendment,
+    EffectiveDateOutsideTerm,
+    CurrencyMismatch,
+    TermNotEnded,
+}
+
+impl std::error::Error for ContractError {}
+
+impl std::fmt::Display for ContractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DealNotWon => write!(f, "Contract requires a won deal"),
+            Self::InvalidTerm => write!(f, "End date must be after the start date"),
+            Self::NotDraft => write!(f, "Contract is not a draft"),
+            Self::NotActive => write!(f, "Contract is not active"),
+            Self::NotAmendable => write!(f, "Contract can no longer be amended"),
+            Self::EmptyAmendment => write!(f, "Amendment has no changes"),
+            Self::EffectiveDateOutsideTerm => write!(f, "Effective date is outside the contract term"),
+            Self::CurrencyMismatch => write!(f, "Currency mismatch"),
+            Self::TermNotEnded => write!(f, "Contract term has not ended"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+    
+    fn won_deal() -> Deal {
+        let mut deal = Deal::create(
+            "Enterprise SASE",
+            Money::usd(Decimal::new(120000, 0)),
+            EntityId::new(),
+            EntityId::new(),
+            EntityId::new(),
+        );
+        deal.close_won().unwrap();
+        deal
+    }
+    
+    fn create_test_contract() -> Contract {
+        let mut terms = ContractTerms::new(date(2025, 12, 31), Money::usd(Decimal::new(120000, 0)));
+        terms.entitlements.push(Entitlement {
+            sku: "ZTNA-USER".into(),
+            description: "ZTNA seats".into(),
+            quantity: 500,
+        });
+        terms.service_levels.push(ServiceLevel {
+            metric: SlaMetric::Availability,
+            target: Decimal::new(9995, 2),
+            credit_percent: Decimal::new(10, 0),
+        });
+        
+        let mut contract = Contract::create(
+            "Enterprise SASE 2025",
+            &won_deal(),
+            date(2025, 1, 1),
+            terms,
+            EntityId::new(),
+        ).unwrap();
+        contract.activate("sub-123").unwrap();
+        contract
+    }
+    
+    #[test]
+    fn test_requires_won_deal() {
+        let deal = Deal::create(
+            "Open Deal",
+            Money::usd(Decimal::new(1000, 0)),
+            EntityId::new(),
+            EntityId::new(),
+            EntityId::new(),
+        );
+        let terms = ContractTerms::new(date(2025, 12, 31), Money::usd(Decimal::new(1000, 0)));
+        
+        assert!(matches!(
+            Contract::create("C", &deal, date(2025, 1, 1), terms, EntityId::new()),
+            Err(ContractError::DealNotWon)
+        ));
+    }
+    
+    #[test]
+    fn test_activate_links_subscription() {
+        let contract = create_test_contract();
+        assert!(contract.is_active());
+        assert_eq!(contract.subscription_id(), Some("sub-123"));
+    }
+    
+    #[test]
+    fn test_amendments_are_effective_dated() {
+        let mut contract = create_test_contract();
+        contract.amend(
+            date(2025, 7, 1),
+            vec![TermChange::SetEntitlement(Entitlement {
+                sku: "ZTNA-USER".into(),
+                description: "ZTNA seats".into(),
+                quantity: 750,
+            })],
+            "Seat expansion",
+            EntityId::new(),
+        ).unwrap();
+        
+        // Back-dated amendment recorded later still applies first
+        contract.amend(
+            date(2025, 3, 1),
+            vec![TermChange::CommittedSpend(Money::usd(Decimal::new(150000, 0)))],
+            "Uplift",
+            EntityId::new(),
+        ).unwrap();
+        
+        assert_eq!(contract.amendments()[0].sequence, 2);
+        
+        let before = contract.terms_as_of(date(2025, 2, 1));
+        assert_eq!(before.entitlement("ZTNA-USER").unwrap().quantity, 500);
+        assert_eq!(before.committed_spend.amount(), Decimal::new(120000, 0));
+        
+        let after = contract.terms_as_of(date(2025, 8, 1));
+        assert_eq!(after.entitlement("ZTNA-USER").unwrap().quantity, 750);
+        assert_eq!(after.committed_spend.amount(), Decimal::new(150000, 0));
+        
+        // Original terms are never rewritten
+        assert_eq!(contract.original_terms().entitlement("ZTNA-USER").unwrap().quantity, 500);
+    }
+    
+    #[test]
+    fn test_amendment_validation() {
+        let mut contract = create_test_contract();
+        
+        assert!(matches!(
+            contract.amend(date(2026, 6, 1), vec![TermChange::AutoRenew(true)], "", EntityId::new()),
+            Err(ContractError::EffectiveDateOutsideTerm)
+        ));
+        assert!(matches!(
+            contract.amend(date(2025, 6, 1), vec![], "", EntityId::new()),
+            Err(ContractError::EmptyAmendment)
+        ));
+        assert!(matches!(
+            contract.amend(
+                date(2025, 6, 1),
+                vec![TermChange::CommittedSpend(Money::new(Decimal::ONE, crate::domain::value_objects::Currency::EUR))],
+                "",
+                EntityId::new(),
+            ),
+            Err(ContractError::CurrencyMismatch)
+        ));
+    }
+    
+    #[test]
+    fn test_renewal_reminders() {
+        let mut contract = create_test_contract();
+        
+        assert!(contract.record_renewal_reminder(date(2025, 9, 1)).is_none());
+        
+        // 91 days out: nothing; 90 days out: first reminder
+        assert!(contract.record_renewal_reminder(date(2025, 10, 1)).is_none());
+        let first = contract.record_renewal_reminder(date(2025, 10, 2)).unwrap();
+        assert_eq!(first.days_before, 90);
+        assert_eq!(first.follow_up_by(), date(2025, 11, 1));
+        assert!(contract.record_renewal_reminder(date(2025, 10, 3)).is_none());
+        
+        // Missed the 60-day run: only the 30-day reminder fires
+        let late = contract.record_renewal_reminder(date(2025, 12, 5)).unwrap();
+        assert_eq!(late.days_before, 30);
+        assert_eq!(late.follow_up_by(), date(2025, 12, 31));
+        assert!(contract.record_renewal_reminder(date(2025, 11, 1)).is_none());
+    }
+    
+    #[test]
+    fn test_extension_rearms_reminders() {
+        let mut contract = create_test_contract();
+        contract.record_renewal_reminder(date(2025, 12, 1)).unwrap();
+        
+        contract.amend(
+            date(2025, 12, 1),
+            vec![TermChange::EndDate(date(2026, 3, 31))],
+            "Extension",
+            EntityId::new(),
+        ).unwrap();
+        
+        assert!(contract.record_renewal_reminder(date(2025, 12, 15)).is_none());
+        let reminder = contract.record_renewal_reminder(date(2026, 1, 1)).unwrap();
+        assert_eq!(reminder.days_before, 90);
+        assert_eq!(reminder.end_date, date(2026, 3, 31));
+    }
+    
+    #[test]
+    fn test_expire() {
+        let mut contract = create_test_contract();
+        assert!(matches!(contract.expire(date(2025, 12, 31)), Err(ContractError::TermNotEnded)));
+        contract.expire(date(2026, 1, 1)).unwrap();
+        assert_eq!(contract.status(), &ContractStatus::Expired);
+        assert!(contract.record_renewal_reminder(date(2026, 1, 1)).is_none());
+    }
+}
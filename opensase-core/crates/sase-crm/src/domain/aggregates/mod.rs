@@ -1,7 +1,14 @@
 //! Aggregates module
 
 pub mod contact;
+pub mod contract;
 pub mod deal;
+pub mod task;
 
 pub use contact::{Contact, ContactError, LeadStatus, LifecycleStage, LeadScore};
 pub use deal::{Deal, DealError, DealStatus, DealType, Probability, DealProduct, Competitor};
+pub use contract::{
+    Contract, ContractError, ContractStatus, ContractTerms, Amendment, TermChange,
+    ServiceLevel, SlaMetric, Entitlement, RenewalReminder, RENEWAL_REMINDER_DAYS,
+};
+pub use task::{Task, TaskError, TaskStatus, TaskPriority, RelatedRecord};
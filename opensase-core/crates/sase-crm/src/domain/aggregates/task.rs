@@ -0,0 +1,178 @@
+//! Task Aggregate
+//!
+//! Follow-up work assigned to a user and attached to a CRM record.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::value_objects::EntityId;
+use crate::domain::events::{DomainEvent, TaskEvent};
+
+/// Task aggregate root
+#[derive(Clone, Debug)]
+pub struct Task {
+    id: EntityId,
+    subject: String,
+    description: Option<String>,
+    related_to: RelatedRecord,
+    owner_id: EntityId,
+    due_date: Option<NaiveDate>,
+    priority: TaskPriority,
+    status: TaskStatus,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    events: Vec<DomainEvent>,
+}
+
+impl Task {
+    /// Create a new open task
+    pub fn create(
+        subject: impl Into<String>,
+        related_to: RelatedRecord,
+        owner_id: EntityId,
+        due_date: Option<NaiveDate>,
+    ) -> Self {
+        let now = Utc::now();
+        let id = EntityId::new();
+        
+        let mut task = Self {
+            id: id.clone(),
+            subject: subject.into(),
+            description: None,
+            related_to,
+            owner_id: owner_id.clone(),
+            due_date,
+            priority: TaskPriority::Normal,
+            status: TaskStatus::Open,
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            events: vec![],
+        };
+        
+        task.raise_event(DomainEvent::Task(TaskEvent::Created {
+            task_id: id,
+            subject: task.subject.clone(),
+            owner_id,
+            due_date,
+            created_at: now,
+        }));
+        
+        task
+    }
+    
+    // =========================================================================
+    // Getters
+    // =========================================================================
+    
+    pub fn id(&self) -> &EntityId { &self.id }
+    pub fn subject(&self) -> &str { &self.subject }
+    pub fn description(&self) -> Option<&str> { self.description.as_deref() }
+    pub fn related_to(&self) -> &RelatedRecord { &self.related_to }
+    pub fn owner_id(&self) -> &EntityId { &self.owner_id }
+    pub fn due_date(&self) -> Option<NaiveDate> { self.due_date }
+    pub fn priority(&self) -> &TaskPriority { &self.priority }
+    pub fn status(&self) -> &TaskStatus { &self.status }
+    pub fn is_open(&self) -> bool { self.status == TaskStatus::Open }
+    pub fn created_at(&self) -> DateTime<Utc> { self.created_at }
+    pub fn completed_at(&self) -> Option<DateTime<Utc>> { self.completed_at }
+    
+    /// Open and past its due date
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.is_open() && self.due_date.is_some_and(|d| d < today)
+    }
+    
+    // =========================================================================
+    // Business Operations
+    // =========================================================================
+    
+    /// Set description
+    pub fn set_description(&mut self, description: impl Into<String>) {
+        self.description = Some(description.into());
+        self.touch();
+    }
+    
+    /// Set priority
+    pub fn set_priority(&mut self, priority: TaskPriority) {
+        self.priority = priority;
+        self.touch();
+    }
+    
+    /// Mark task complete
+    pub fn complete(&mut self) -> Result<(), TaskError> {
+        if !self.is_open() {
+            return Err(TaskError::AlreadyCompleted);
+        }
+        
+        let now = Utc::now();
+        
+        self.status = TaskStatus::Completed;
+        self.completed_at = Some(now);
+        self.touch();
+        
+        self.raise_event(DomainEvent::Task(TaskEvent::Completed {
+            task_id: self.id.clone(),
+            completed_at: now,
+        }));
+        
+        Ok(())
+    }
+    
+    // =========================================================================
+    // Private
+    // =========================================================================
+    
+    pub fn take_events(&mut self) -> Vec<DomainEvent> {
+        std::mem::take(&mut self.events)
+    }
+    
+    fn raise_event(&mut self, event: DomainEvent) {
+        self.events.push(event);
+    }
+    
+    fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+}
+
+// =============================================================================
+// Supporting Types
+// =============================================================================
+
+/// CRM record a task is attached to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelatedRecord {
+    Contact(EntityId),
+    Account(EntityId),
+    Deal(EntityId),
+    Contract(EntityId),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskPriority {
+    Low,
+    Normal,
+    High,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    Open,
+    Completed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    AlreadyCompleted,
+}
+
+impl std::error::Error for TaskError {}
+
+impl std::fmt::Display for TaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlreadyCompleted => write!(f, "Task is already completed"),
+        }
+    }
+}
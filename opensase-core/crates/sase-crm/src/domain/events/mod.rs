@@ -2,7 +2,7 @@
 //!
 //! Events raised by aggregates to communicate state changes.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use crate::domain::value_objects::{Email, EntityId};
 use crate::domain::aggregates::contact::LeadStatus;
 
@@ -12,6 +12,8 @@ pub enum DomainEvent {
     Contact(ContactEvent),
     Deal(DealEvent),
     Account(AccountEvent),
+    Contract(ContractEvent),
+    Task(TaskEvent),
 }
 
 /// Contact-related domain events
@@ -112,6 +114,73 @@ pub enum AccountEvent {
     },
 }
 
+/// Contract-related domain events
+#[derive(Clone, Debug)]
+pub enum ContractEvent {
+    Created {
+        contract_id: EntityId,
+        deal_id: EntityId,
+        committed_spend: rust_decimal::Decimal,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        owner_id: EntityId,
+        created_at: DateTime<Utc>,
+    },
+    
+    Activated {
+        contract_id: EntityId,
+        subscription_id: String,
+        activated_at: DateTime<Utc>,
+    },
+    
+    Amended {
+        contract_id: EntityId,
+        sequence: u32,
+        effective_date: NaiveDate,
+        recorded_at: DateTime<Utc>,
+    },
+    
+    RenewalReminderDue {
+        contract_id: EntityId,
+        days_before: u32,
+        end_date: NaiveDate,
+        owner_id: EntityId,
+    },
+    
+    Renewed {
+        contract_id: EntityId,
+        renewal_contract_id: EntityId,
+    },
+    
+    Expired {
+        contract_id: EntityId,
+        expired_on: NaiveDate,
+    },
+    
+    Terminated {
+        contract_id: EntityId,
+        reason: String,
+        terminated_at: DateTime<Utc>,
+    },
+}
+
+/// Task-related domain events
+#[derive(Clone, Debug)]
+pub enum TaskEvent {
+    Created {
+        task_id: EntityId,
+        subject: String,
+        owner_id: EntityId,
+        due_date: Option<NaiveDate>,
+        created_at: DateTime<Utc>,
+    },
+    
+    Completed {
+        task_id: EntityId,
+        completed_at: DateTime<Utc>,
+    },
+}
+
 impl DomainEvent {
     /// Get the aggregate ID this event belongs to
     pub fn aggregate_id(&self) -> &EntityId {
@@ -136,6 +205,19 @@ impl DomainEvent {
                 AccountEvent::ContactLinked { account_id, .. } => account_id,
                 AccountEvent::DealLinked { account_id, .. } => account_id,
             },
+            DomainEvent::Contract(e) => match e {
+                ContractEvent::Created { contract_id, .. } => contract_id,
+                ContractEvent::Activated { contract_id, .. } => contract_id,
+                ContractEvent::Amended { contract_id, .. } => contract_id,
+                ContractEvent::RenewalReminderDue { contract_id, .. } => contract_id,
+                ContractEvent::Renewed { contract_id, .. } => contract_id,
+                ContractEvent::Expired { contract_id, .. } => contract_id,
+                ContractEvent::Terminated { contract_id, .. } => contract_id,
+            },
+            DomainEvent::Task(e) => match e {
+                TaskEvent::Created { task_id, .. } => task_id,
+                TaskEvent::Completed { task_id, .. } => task_id,
+            },
         }
     }
     
@@ -162,6 +244,19 @@ impl DomainEvent {
                 AccountEvent::ContactLinked { .. } => "account.contact_linked",
                 AccountEvent::DealLinked { .. } => "account.deal_linked",
             },
+            DomainEvent::Contract(e) => match e {
+                ContractEvent::Created { .. } => "contract.created",
+                ContractEvent::Activated { .. } => "contract.activated",
+                ContractEvent::Amended { .. } => "contract.amended",
+                ContractEvent::RenewalReminderDue { .. } => "contract.renewal_reminder_due",
+                ContractEvent::Renewed { .. } => "contract.renewed",
+                ContractEvent::Expired { .. } => "contract.expired",
+                ContractEvent::Terminated { .. } => "contract.terminated",
+            },
+            DomainEvent::Task(e) => match e {
+                TaskEvent::Created { .. } => "task.created",
+                TaskEvent::Completed { .. } => "task.completed",
+            },
         }
     }
 }
@@ -4,10 +4,13 @@ use std::collections::HashMap;
 use std::sync::RwLock;
 use async_trait::async_trait;
 
-use crate::domain::aggregates::{Contact, Deal};
+use crate::domain::aggregates::{Contact, Contract, Deal, RelatedRecord, Task};
 use crate::domain::value_objects::{Email, EntityId};
 use crate::domain::DomainEvent;
-use crate::ports::outbound::{ContactRepository, DealRepository, EventPublisher, RepositoryError};
+use crate::ports::outbound::{
+    ContactRepository, ContractRepository, DealRepository, EventPublisher, RepositoryError,
+    TaskRepository,
+};
 
 /// In-memory contact repository (for testing)
 #[derive(Default)]
@@ -179,6 +182,105 @@ impl DealRepository for InMemoryDealRepository {
     }
 }
 
+/// In-memory contract repository (for testing)
+#[derive(Default)]
+pub struct InMemoryContractRepository {
+    contracts: RwLock<HashMap<String, Contract>>,
+}
+
+impl InMemoryContractRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ContractRepository for InMemoryContractRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Contract>, RepositoryError> {
+        let contracts = self.contracts.read().unwrap();
+        Ok(contracts.get(id.as_str()).cloned())
+    }
+    
+    async fn find_by_deal(&self, deal_id: &EntityId) -> Result<Vec<Contract>, RepositoryError> {
+        let contracts = self.contracts.read().unwrap();
+        Ok(contracts.values()
+            .filter(|c| c.deal_id() == deal_id)
+            .cloned()
+            .collect())
+    }
+    
+    async fn find_by_account(&self, account_id: &EntityId) -> Result<Vec<Contract>, RepositoryError> {
+        let contracts = self.contracts.read().unwrap();
+        Ok(contracts.values()
+            .filter(|c| c.account_id().map(|a| a == account_id).unwrap_or(false))
+            .cloned()
+            .collect())
+    }
+    
+    async fn find_by_subscription(&self, subscription_id: &str) -> Result<Option<Contract>, RepositoryError> {
+        let contracts = self.contracts.read().unwrap();
+        Ok(contracts.values()
+            .find(|c| c.subscription_id() == Some(subscription_id))
+            .cloned())
+    }
+    
+    async fn find_active(&self) -> Result<Vec<Contract>, RepositoryError> {
+        let contracts = self.contracts.read().unwrap();
+        Ok(contracts.values()
+            .filter(|c| c.is_active())
+            .cloned()
+            .collect())
+    }
+    
+    async fn save(&self, contract: &Contract) -> Result<(), RepositoryError> {
+        let mut contracts = self.contracts.write().unwrap();
+        contracts.insert(contract.id().to_string(), contract.clone());
+        Ok(())
+    }
+}
+
+/// In-memory task repository (for testing)
+#[derive(Default)]
+pub struct InMemoryTaskRepository {
+    tasks: RwLock<HashMap<String, Task>>,
+}
+
+impl InMemoryTaskRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TaskRepository for InMemoryTaskRepository {
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Task>, RepositoryError> {
+        let tasks = self.tasks.read().unwrap();
+        Ok(tasks.get(id.as_str()).cloned())
+    }
+    
+    async fn find_open_by_owner(&self, owner_id: &EntityId) -> Result<Vec<Task>, RepositoryError> {
+        let tasks = self.tasks.read().unwrap();
+        Ok(tasks.values()
+            .filter(|t| t.is_open() && t.owner_id() == owner_id)
+            .cloned()
+            .collect())
+    }
+    
+    async fn find_by_contract(&self, contract_id: &EntityId) -> Result<Vec<Task>, RepositoryError> {
+        let tasks = self.tasks.read().unwrap();
+        Ok(tasks.values()
+            .filter(|t| matches!(t.related_to(), RelatedRecord::Contract(id) if id == contract_id))
+            .cloned()
+            .collect())
+    }
+    
+    async fn save(&self, task: &Task) -> Result<(), RepositoryError> {
+        let mut tasks = self.tasks.write().unwrap();
+        tasks.insert(task.id().to_string(), task.clone());
+        Ok(())
+    }
+}
+
 /// No-op event publisher for testing
 #[derive(Default)]
 pub struct NoOpEventPublisher;
@@ -240,4 +342,59 @@ mod tests {
         let found = repo.find_by_id(deal.id()).await.unwrap();
         assert!(found.is_some());
     }
+    
+    #[tokio::test]
+    async fn test_contract_renewal_reminders_create_tasks() {
+        use std::sync::Arc;
+        use chrono::NaiveDate;
+        use crate::application::{ContractService, CreateContractCommand};
+        use crate::ports::inbound::ContractUseCases;
+        
+        let deal_repo = Arc::new(InMemoryDealRepository::new());
+        let contract_repo = Arc::new(InMemoryContractRepository::new());
+        let task_repo = Arc::new(InMemoryTaskRepository::new());
+        let service = ContractService::new(
+            contract_repo.clone(),
+            deal_repo.clone(),
+            task_repo.clone(),
+            Arc::new(NoOpEventPublisher),
+        );
+        
+        let mut deal = Deal::create(
+            "Renewal Deal",
+            Money::usd(Decimal::new(50000, 0)),
+            EntityId::new(),
+            EntityId::new(),
+            EntityId::new(),
+        );
+        deal.close_won().unwrap();
+        deal_repo.save(&deal).await.unwrap();
+        
+        let owner = EntityId::new();
+        let contract = service.create_contract(CreateContractCommand {
+            deal_id: deal.id().to_string(),
+            name: "Acme 2025".into(),
+            start_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+            auto_renew: false,
+            committed_spend: Decimal::new(50000, 0),
+            currency: "USD".into(),
+            service_levels: vec![],
+            entitlements: vec![],
+            owner_id: owner.to_string(),
+        }).await.unwrap();
+        service.activate_contract(contract.id(), "sub-1").await.unwrap();
+        
+        let today = NaiveDate::from_ymd_opt(2025, 10, 15).unwrap();
+        let tasks = service.run_renewal_reminders(today).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].owner_id(), &owner);
+        
+        // Re-running the same day does not duplicate the task
+        assert!(service.run_renewal_reminders(today).await.unwrap().is_empty());
+        assert_eq!(task_repo.find_by_contract(contract.id()).await.unwrap().len(), 1);
+        
+        let stored = contract_repo.find_by_id(contract.id()).await.unwrap().unwrap();
+        assert_eq!(stored.renewal_reminders().len(), 1);
+    }
 }
@@ -15,12 +15,14 @@
 //! - **Contact**: Lead/contact management with lifecycle stages
 //! - **Deal**: Sales opportunity with pipeline stages
 //! - **Account**: Company/organization management
+//! - **Contract**: Signed terms from a won deal, with amendments and renewals
 //!
 //! ## Features
 //!
 //! - Contact and account management with 360° view
 //! - Sales pipeline with customizable stages
 //! - Opportunity tracking and forecasting
+//! - Contract management with renewal reminder tasks
 //! - AI-powered lead scoring
 //! - Domain events for integration
 
@@ -30,12 +32,12 @@ pub mod ports;
 pub mod infrastructure;
 
 // Re-exports for convenience
-pub use domain::aggregates::{Contact, Deal, LeadStatus, LifecycleStage, DealStatus};
+pub use domain::aggregates::{Contact, Contract, ContractStatus, ContractTerms, Deal, LeadStatus, LifecycleStage, DealStatus, Task};
 pub use domain::value_objects::{Email, Money, Currency, Phone, Address, EntityId};
-pub use domain::events::{DomainEvent, ContactEvent, DealEvent};
-pub use application::{ContactService, DealService};
-pub use ports::inbound::{ContactUseCases, DealUseCases, UseCaseError};
-pub use ports::outbound::{ContactRepository, DealRepository, RepositoryError};
+pub use domain::events::{DomainEvent, ContactEvent, ContractEvent, DealEvent, TaskEvent};
+pub use application::{ContactService, ContractService, DealService};
+pub use ports::inbound::{ContactUseCases, ContractUseCases, DealUseCases, UseCaseError};
+pub use ports::outbound::{ContactRepository, ContractRepository, DealRepository, RepositoryError, TaskRepository};
 
 // Legacy module stubs (removed, now using DDD structure)
 pub mod contacts { pub use crate::domain::aggregates::contact::*; }
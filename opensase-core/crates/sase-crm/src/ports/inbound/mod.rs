@@ -3,7 +3,7 @@
 //! Hexagonal architecture: application service interfaces.
 
 use async_trait::async_trait;
-use crate::domain::aggregates::{Contact, Deal};
+use crate::domain::aggregates::{Contact, Contract, ContractTerms, Deal, Task};
 use crate::domain::value_objects::{EntityId, Email};
 use crate::application::dto::*;
 
@@ -57,6 +57,31 @@ pub trait DealUseCases: Send + Sync {
     async fn get_forecast(&self, owner_id: Option<&EntityId>) -> Result<ForecastView, UseCaseError>;
 }
 
+/// Contract management use cases
+#[async_trait]
+pub trait ContractUseCases: Send + Sync {
+    /// Create a draft contract from a won deal
+    async fn create_contract(&self, command: CreateContractCommand) -> Result<Contract, UseCaseError>;
+    
+    /// Activate contract once its billing subscription is provisioned
+    async fn activate_contract(&self, contract_id: &EntityId, subscription_id: &str) -> Result<Contract, UseCaseError>;
+    
+    /// Record an effective-dated amendment
+    async fn amend_contract(&self, command: AmendContractCommand) -> Result<Contract, UseCaseError>;
+    
+    /// Terminate contract early
+    async fn terminate_contract(&self, contract_id: &EntityId, reason: String) -> Result<Contract, UseCaseError>;
+    
+    /// Get contract by ID
+    async fn get_contract(&self, id: &EntityId) -> Result<Option<Contract>, UseCaseError>;
+    
+    /// Get the terms in force on a date
+    async fn get_terms_as_of(&self, contract_id: &EntityId, date: chrono::NaiveDate) -> Result<ContractTerms, UseCaseError>;
+    
+    /// Raise due renewal reminders as tasks for contract owners
+    async fn run_renewal_reminders(&self, today: chrono::NaiveDate) -> Result<Vec<Task>, UseCaseError>;
+}
+
 #[derive(Debug, Clone)]
 pub enum UseCaseError {
     NotFound(String),
@@ -3,7 +3,7 @@
 //! Hexagonal architecture: these are the interfaces that infrastructure must implement.
 
 use async_trait::async_trait;
-use crate::domain::aggregates::{Contact, Deal};
+use crate::domain::aggregates::{Contact, Contract, Deal, Task};
 use crate::domain::value_objects::{EntityId, Email};
 
 /// Contact repository port
@@ -72,6 +72,44 @@ pub trait DealRepository: Send + Sync {
     async fn delete(&self, id: &EntityId) -> Result<(), RepositoryError>;
 }
 
+/// Contract repository port
+#[async_trait]
+pub trait ContractRepository: Send + Sync {
+    /// Find contract by ID
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Contract>, RepositoryError>;
+    
+    /// Find contracts created from a deal
+    async fn find_by_deal(&self, deal_id: &EntityId) -> Result<Vec<Contract>, RepositoryError>;
+    
+    /// Find contracts by account
+    async fn find_by_account(&self, account_id: &EntityId) -> Result<Vec<Contract>, RepositoryError>;
+    
+    /// Find contract by billing subscription
+    async fn find_by_subscription(&self, subscription_id: &str) -> Result<Option<Contract>, RepositoryError>;
+    
+    /// Find active contracts
+    async fn find_active(&self) -> Result<Vec<Contract>, RepositoryError>;
+    
+    /// Save contract
+    async fn save(&self, contract: &Contract) -> Result<(), RepositoryError>;
+}
+
+/// Task repository port
+#[async_trait]
+pub trait TaskRepository: Send + Sync {
+    /// Find task by ID
+    async fn find_by_id(&self, id: &EntityId) -> Result<Option<Task>, RepositoryError>;
+    
+    /// Find open tasks by owner
+    async fn find_open_by_owner(&self, owner_id: &EntityId) -> Result<Vec<Task>, RepositoryError>;
+    
+    /// Find tasks attached to a contract
+    async fn find_by_contract(&self, contract_id: &EntityId) -> Result<Vec<Task>, RepositoryError>;
+    
+    /// Save task
+    async fn save(&self, task: &Task) -> Result<(), RepositoryError>;
+}
+
 /// Event publisher port
 #[async_trait]
 pub trait EventPublisher: Send + Sync {
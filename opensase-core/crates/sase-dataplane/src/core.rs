@@ -3,6 +3,7 @@
 //! Run-to-completion packet processing with per-core isolation.
 
 use crate::{FlowTable, Pipeline, BufferPool, BATCH_SIZE};
+use crate::update::{ConfigManager, CoreConfig, UpdateSettings};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -63,6 +64,7 @@ pub struct FastPathEngine {
     running: Arc<AtomicBool>,
     workers: Vec<WorkerHandle>,
     stats: Arc<EngineStats>,
+    config_manager: Arc<ConfigManager>,
 }
 
 /// Per-worker handle
@@ -110,6 +112,7 @@ impl FastPathEngine {
     /// Create new engine with config
     pub fn new(config: EngineConfig) -> Self {
         Self {
+            config_manager: ConfigManager::new(config.num_cores, UpdateSettings::default()),
             config,
             running: Arc::new(AtomicBool::new(false)),
             workers: Vec::new(),
@@ -132,6 +135,7 @@ impl FastPathEngine {
                 self.config.clone(),
                 self.running.clone(),
                 self.stats.clone(),
+                self.config_manager.core_handle(core_id),
             );
 
             let handle = thread::Builder::new()
@@ -172,6 +176,11 @@ impl FastPathEngine {
         self.running.load(Ordering::Acquire)
     }
 
+    /// Config manager for make-before-break ACL and SA updates
+    pub fn config_manager(&self) -> &Arc<ConfigManager> {
+        &self.config_manager
+    }
+
     /// Get engine stats
     pub fn stats(&self) -> EngineStatsSnapshot {
         EngineStatsSnapshot {
@@ -230,6 +239,7 @@ struct Worker {
     flow_table: FlowTable,
     pipeline: Pipeline,
     buffer_pool: BufferPool,
+    dp_config: CoreConfig,
 }
 
 impl Worker {
@@ -238,6 +248,7 @@ impl Worker {
         config: EngineConfig,
         running: Arc<AtomicBool>,
         stats: Arc<EngineStats>,
+        dp_config: CoreConfig,
     ) -> Self {
         Self {
             core_id,
@@ -247,6 +258,7 @@ impl Worker {
             flow_table: FlowTable::new(config.flow_table_size),
             pipeline: Pipeline::new(),
             buffer_pool: BufferPool::new(config.buffer_pool_size),
+            dp_config,
         }
     }
 
@@ -277,12 +289,17 @@ impl Worker {
         // 2. Look up flow for each packet
         // 3. Apply pipeline transformations
         // 4. Enqueue to TX queue
-        
+        //
+        // Config is only swapped between batches
+        self.dp_config.begin_batch();
+
         // For now, simulate batch processing
         self.stats.cycles.fetch_add(1, Ordering::Relaxed);
         
         // Small yield to prevent busy-spinning in simulation
         std::hint::spin_loop();
+
+        self.dp_config.end_batch(0);
     }
 
     #[cfg(target_os = "linux")]
@@ -330,6 +347,27 @@ mod tests {
         assert!(!engine.is_running());
     }
 
+    #[test]
+    fn test_config_swap_running_engine() {
+        let config = EngineConfig {
+            num_cores: 2,
+            ..Default::default()
+        };
+
+        let mut engine = FastPathEngine::new(config);
+        engine.start().unwrap();
+
+        let version = engine.config_manager()
+            .apply(&crate::update::DataplaneConfig::default())
+            .unwrap();
+        assert_eq!(version, 1);
+        assert!(engine.config_manager()
+            .wait_for_swap(std::time::Duration::from_secs(5))
+            .is_some());
+
+        engine.stop();
+    }
+
     #[test]
     fn test_stats() {
         let config = EngineConfig {
@@ -0,0 +1,811 @@
+//! Encrypted Traffic Analysis (ETA) Feature Extraction
+//!
+//! Collects per-flow features for classifying TLS traffic that cannot be
+//! decrypted:
+//!
+//! - **SPLT**: sequence of packet lengths and inter-arrival times for the
+//!   first packets of a flow
+//! - **Handshake metadata**: JA4 fingerprint, SNI and ALPN from the cleartext
+//!   ClientHello, and certificate validity from TLS ≤1.2 server certificates
+//!
+//! Records are exported once the SPLT window fills (early classification)
+//! and again when the flow ends, carrying final byte totals. The record types
+//! live in `sase_common::eta`, shared with the inference engine.
+
+use crate::buffer::PacketBuffer;
+use crate::flow::{timestamp_micros, FlowKey};
+use crate::pipeline::{PipelineContext, Stage, StageResult};
+use crossbeam::channel::Sender;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub use sase_common::eta::{CertificateInfo, Direction, EtaRecord, PacketSample};
+
+/// Default number of packets in the SPLT window
+pub const SPLT_PACKETS: usize = 20;
+
+const TLS_HANDSHAKE: u8 = 0x16;
+const HS_CLIENT_HELLO: u8 = 0x01;
+const HS_CERTIFICATE: u8 = 0x0b;
+
+const EXT_SNI: u16 = 0x0000;
+const EXT_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXT_ALPN: u16 = 0x0010;
+const EXT_SUPPORTED_VERSIONS: u16 = 0x002b;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_RST: u8 = 0x04;
+
+/// Cleartext TLS ClientHello metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHello {
+    /// Highest offered version (supported_versions if present)
+    pub version: u16,
+    /// Cipher suites, GREASE removed, in offered order
+    pub cipher_suites: Vec<u16>,
+    /// Extension types, GREASE removed, in offered order
+    pub extensions: Vec<u16>,
+    /// Signature algorithms in offered order
+    pub signature_algorithms: Vec<u16>,
+    /// Server name indication
+    pub sni: Option<String>,
+    /// ALPN protocols in offered order
+    pub alpn: Vec<String>,
+}
+
+impl ClientHello {
+    /// Parse a ClientHello from the start of a TCP payload
+    ///
+    /// Hellos split across segments are parsed up to the bytes available.
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        if payload.len() < 9 || payload[0] != TLS_HANDSHAKE || payload[1] != 0x03 {
+            return None;
+        }
+        if payload[5] != HS_CLIENT_HELLO {
+            return None;
+        }
+
+        let mut r = Reader::new(&payload[9..]);
+        let legacy_version = r.u16()?;
+        r.skip(32)?; // random
+        let session_id_len = r.u8()? as usize;
+        r.skip(session_id_len)?;
+
+        let cipher_len = r.u16()? as usize;
+        let mut ciphers = Reader::new(r.take(cipher_len)?);
+        let mut hello = ClientHello {
+            version: legacy_version,
+            ..Default::default()
+        };
+        while let Some(cipher) = ciphers.u16() {
+            if !is_grease(cipher) {
+                hello.cipher_suites.push(cipher);
+            }
+        }
+
+        let compression_len = r.u8()? as usize;
+        r.skip(compression_len)?;
+
+        // Extensions are optional and may be truncated by segmentation
+        let Some(ext_len) = r.u16() else {
+            return Some(hello);
+        };
+        let mut exts = Reader::new(r.take_up_to(ext_len as usize));
+        while let (Some(ext_type), Some(len)) = (exts.u16(), exts.u16()) {
+            if !is_grease(ext_type) {
+                hello.extensions.push(ext_type);
+            }
+            let Some(data) = exts.take(len as usize) else {
+                break;
+            };
+            hello.parse_extension(ext_type, data);
+        }
+
+        Some(hello)
+    }
+
+    fn parse_extension(&mut self, ext_type: u16, data: &[u8]) {
+        let mut r = Reader::new(data);
+        match ext_type {
+            EXT_SNI => {
+                // server_name_list: (type u8, name u16-prefixed)*
+                let _list_len = r.u16();
+                while let (Some(name_type), Some(len)) = (r.u8(), r.u16()) {
+                    let Some(name) = r.take(len as usize) else { break };
+                    if name_type == 0 {
+                        self.sni = std::str::from_utf8(name).ok().map(|s| s.to_ascii_lowercase());
+                        break;
+                    }
+                }
+            }
+            EXT_ALPN => {
+                let _list_len = r.u16();
+                while let Some(len) = r.u8() {
+                    let Some(proto) = r.take(len as usize) else { break };
+                    self.alpn.push(String::from_utf8_lossy(proto).into_owned());
+                }
+            }
+            EXT_SIGNATURE_ALGORITHMS => {
+                let _list_len = r.u16();
+                while let Some(alg) = r.u16() {
+                    self.signature_algorithms.push(alg);
+                }
+            }
+            EXT_SUPPORTED_VERSIONS => {
+                let _list_len = r.u8();
+                while let Some(v) = r.u16() {
+                    if !is_grease(v) && v > self.version {
+                        self.version = v;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// JA4 fingerprint (TCP)
+    pub fn ja4(&self) -> String {
+        let version = match self.version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let sni = if self.sni.is_some() { 'd' } else { 'i' };
+        let alpn = self.alpn.first()
+            .filter(|a| !a.is_empty())
+            .map(|a| {
+                let first = a.chars().next().unwrap_or('0');
+                let last = a.chars().last().unwrap_or('0');
+                format!("{}{}", first, last)
+            })
+            .unwrap_or_else(|| "00".to_string());
+
+        let ja4_a = format!(
+            "t{}{}{:02}{:02}{}",
+            version,
+            sni,
+            self.cipher_suites.len().min(99),
+            self.extensions.len().min(99),
+            alpn
+        );
+
+        let mut ciphers = self.cipher_suites.clone();
+        ciphers.sort_unstable();
+        let ja4_b = truncated_sha256(&hex_list(&ciphers));
+
+        let mut exts: Vec<u16> = self.extensions.iter()
+            .copied()
+            .filter(|e| *e != EXT_SNI && *e != EXT_ALPN)
+            .collect();
+        exts.sort_unstable();
+        let mut ja4_c_raw = hex_list(&exts);
+        if !self.signature_algorithms.is_empty() {
+            ja4_c_raw.push('_');
+            ja4_c_raw.push_str(&hex_list(&self.signature_algorithms));
+        }
+        let ja4_c = truncated_sha256(&ja4_c_raw);
+
+        format!("{}_{}_{}", ja4_a, ja4_b, ja4_c)
+    }
+}
+
+/// Find the leaf certificate's validity in server handshake records
+pub fn parse_certificate(payload: &[u8]) -> Option<CertificateInfo> {
+    let mut records = Reader::new(payload);
+    while let (Some(content_type), Some(_version), Some(len)) =
+        (records.u8(), records.u16(), records.u16())
+    {
+        let record = records.take_up_to(len as usize);
+        if content_type != TLS_HANDSHAKE {
+            continue;
+        }
+
+        let mut messages = Reader::new(record);
+        while let (Some(msg_type), Some(msg_len)) = (messages.u8(), messages.u24()) {
+            let message = messages.take_up_to(msg_len as usize);
+            if msg_type == HS_CERTIFICATE {
+                let mut r = Reader::new(message);
+                let _chain_len = r.u24()?;
+                let cert_len = r.u24()? as usize;
+                return parse_x509_validity(r.take_up_to(cert_len));
+            }
+        }
+    }
+    None
+}
+
+/// ETA extractor configuration
+#[derive(Debug, Clone)]
+pub struct EtaConfig {
+    /// Packets in the SPLT window
+    pub splt_packets: usize,
+    /// Maximum tracked flows
+    pub max_flows: usize,
+    /// Idle time before a flow is exported and dropped (microseconds)
+    pub idle_timeout_us: u64,
+    /// Stop tracking flows without a ClientHello after this many packets
+    /// (0 = track all flows)
+    pub handshake_probe_packets: u64,
+}
+
+impl Default for EtaConfig {
+    fn default() -> Self {
+        Self {
+            splt_packets: SPLT_PACKETS,
+            max_flows: 1 << 16,
+            idle_timeout_us: 30_000_000,
+            handshake_probe_packets: 0,
+        }
+    }
+}
+
+/// Per-flow ETA state
+#[derive(Debug, Clone)]
+struct EtaFlow {
+    initiator: FlowKey,
+    start_us: u64,
+    last_us: u64,
+    packets_out: u64,
+    packets_in: u64,
+    bytes_out: u64,
+    bytes_in: u64,
+    splt: Vec<PacketSample>,
+    client_hello: Option<ClientHello>,
+    certificate: Option<CertificateInfo>,
+    splt_exported: bool,
+}
+
+impl EtaFlow {
+    fn new(initiator: FlowKey, now_us: u64, splt_packets: usize) -> Self {
+        Self {
+            initiator,
+            start_us: now_us,
+            last_us: now_us,
+            packets_out: 0,
+            packets_in: 0,
+            bytes_out: 0,
+            bytes_in: 0,
+            splt: Vec::with_capacity(splt_packets),
+            client_hello: None,
+            certificate: None,
+            splt_exported: false,
+        }
+    }
+
+    fn record(&self, complete: bool) -> EtaRecord {
+        let key = &self.initiator;
+        let hello = self.client_hello.as_ref();
+        EtaRecord {
+            src_ip: key.src_ip,
+            dst_ip: key.dst_ip,
+            src_port: key.src_port,
+            dst_port: key.dst_port,
+            protocol: key.protocol,
+            start_us: self.start_us,
+            duration_us: self.last_us.saturating_sub(self.start_us),
+            packets_out: self.packets_out,
+            packets_in: self.packets_in,
+            bytes_out: self.bytes_out,
+            bytes_in: self.bytes_in,
+            splt: self.splt.clone(),
+            ja4: hello.map(|h| h.ja4()),
+            sni: hello.and_then(|h| h.sni.clone()),
+            alpn: hello.and_then(|h| h.alpn.first().cloned()),
+            tls_version: hello.map(|h| h.version),
+            certificate: self.certificate,
+            complete,
+        }
+    }
+}
+
+/// Per-core ETA feature extractor
+pub struct EtaExtractor {
+    config: EtaConfig,
+    /// Flows keyed by their canonical (direction-independent) key
+    flows: HashMap<FlowKey, EtaFlow>,
+    /// Flows not tracked because the table was full
+    dropped: u64,
+}
+
+impl EtaExtractor {
+    /// Create extractor
+    pub fn new(config: EtaConfig) -> Self {
+        Self {
+            flows: HashMap::with_capacity(config.max_flows.min(4096)),
+            config,
+            dropped: 0,
+        }
+    }
+
+    /// Account a packet; returns a record when one is ready for export
+    pub fn observe(
+        &mut self,
+        key: &FlowKey,
+        payload: &[u8],
+        tcp_flags: u8,
+        now_us: u64,
+    ) -> Option<EtaRecord> {
+        let canonical = canonical_key(key);
+        let splt_packets = self.config.splt_packets;
+
+        if !self.flows.contains_key(&canonical) {
+            if self.flows.len() >= self.config.max_flows {
+                self.dropped += 1;
+                return None;
+            }
+            self.flows.insert(canonical, EtaFlow::new(*key, now_us, splt_packets));
+        }
+        let flow = self.flows.get_mut(&canonical)?;
+
+        let direction = if *key == flow.initiator {
+            Direction::Outbound
+        } else {
+            Direction::Inbound
+        };
+        let iat_us = now_us.saturating_sub(flow.last_us).min(u32::MAX as u64) as u32;
+        flow.last_us = now_us;
+
+        let len = payload.len() as u64;
+        match direction {
+            Direction::Outbound => {
+                flow.packets_out += 1;
+                flow.bytes_out += len;
+            }
+            Direction::Inbound => {
+                flow.packets_in += 1;
+                flow.bytes_in += len;
+            }
+        }
+
+        if !payload.is_empty() {
+            if flow.splt.len() < splt_packets {
+                flow.splt.push(PacketSample {
+                    direction,
+                    payload_len: payload.len().min(u16::MAX as usize) as u16,
+                    iat_us,
+                });
+            }
+
+            match direction {
+                Direction::Outbound if flow.client_hello.is_none() => {
+                    flow.client_hello = ClientHello::parse(payload);
+                }
+                Direction::Inbound if flow.certificate.is_none() => {
+                    flow.certificate = parse_certificate(payload);
+                }
+                _ => {}
+            }
+        }
+
+        let probe = self.config.handshake_probe_packets;
+        if probe > 0 && flow.client_hello.is_none() && flow.packets_out + flow.packets_in >= probe {
+            // Not TLS; stop tracking
+            self.flows.remove(&canonical);
+            return None;
+        }
+
+        if tcp_flags & (TCP_FIN | TCP_RST) != 0 {
+            return self.flows.remove(&canonical).map(|f| f.record(true));
+        }
+
+        if !flow.splt_exported && flow.splt.len() >= splt_packets {
+            flow.splt_exported = true;
+            return Some(flow.record(false));
+        }
+
+        None
+    }
+
+    /// Export and drop idle flows
+    pub fn expire(&mut self, now_us: u64) -> Vec<EtaRecord> {
+        let timeout = self.config.idle_timeout_us;
+        let idle: Vec<FlowKey> = self.flows.iter()
+            .filter(|(_, f)| now_us.saturating_sub(f.last_us) > timeout)
+            .map(|(k, _)| *k)
+            .collect();
+
+        idle.into_iter()
+            .filter_map(|k| self.flows.remove(&k))
+            .map(|f| f.record(true))
+            .collect()
+    }
+
+    /// Tracked flows
+    pub fn flow_count(&self) -> usize {
+        self.flows.len()
+    }
+
+    /// Flows skipped because the table was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// Pipeline stage feeding an [`EtaExtractor`]
+///
+/// Records are sent with `try_send`; if the consumer falls behind they are
+/// dropped rather than stalling the fast path.
+pub struct EtaStage {
+    extractor: Mutex<EtaExtractor>,
+    export: Sender<EtaRecord>,
+    export_dropped: AtomicU64,
+}
+
+impl EtaStage {
+    /// Create stage exporting to `export`
+    pub fn new(config: EtaConfig, export: Sender<EtaRecord>) -> Self {
+        Self {
+            extractor: Mutex::new(EtaExtractor::new(config)),
+            export,
+            export_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Export idle flows; call periodically from the worker
+    pub fn expire(&self) {
+        let records = self.extractor.lock().expire(timestamp_micros());
+        for record in records {
+            self.send(record);
+        }
+    }
+
+    /// Records dropped because the export channel was full
+    pub fn export_dropped(&self) -> u64 {
+        self.export_dropped.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, record: EtaRecord) {
+        if self.export.try_send(record).is_err() {
+            self.export_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Stage for EtaStage {
+    fn process(&self, buf: &mut PacketBuffer, ctx: &mut PipelineContext) -> StageResult {
+        let Some(key) = ctx.flow_key else {
+            return StageResult::Continue;
+        };
+
+        let data = buf.data();
+        let l4 = ctx.l4_offset as usize;
+        let (payload_offset, tcp_flags) = match key.protocol {
+            6 if l4 + 14 <= data.len() => (l4 + ((data[l4 + 12] >> 4) as usize) * 4, data[l4 + 13]),
+            17 => (l4 + 8, 0),
+            _ => return StageResult::Continue,
+        };
+        let payload = data.get(payload_offset..).unwrap_or(&[]);
+
+        let record = self.extractor.lock().observe(&key, payload, tcp_flags, timestamp_micros());
+        if let Some(record) = record {
+            self.send(record);
+        }
+
+        StageResult::Continue
+    }
+
+    fn name(&self) -> &'static str { "eta" }
+}
+
+/// Direction-independent flow key
+fn canonical_key(key: &FlowKey) -> FlowKey {
+    if (key.src_ip, key.src_port) <= (key.dst_ip, key.dst_port) {
+        *key
+    } else {
+        key.reverse()
+    }
+}
+
+/// GREASE values (RFC 8701)
+fn is_grease(v: u16) -> bool {
+    (v & 0x0f0f) == 0x0a0a && (v >> 8) == (v & 0xff)
+}
+
+fn hex_list(values: &[u16]) -> String {
+    values.iter()
+        .map(|v| format!("{:04x}", v))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn truncated_sha256(input: &str) -> String {
+    if input.is_empty() {
+        return "000000000000".to_string();
+    }
+    let digest = Sha256::digest(input.as_bytes());
+    digest.iter()
+        .take(6)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Extract validity (and self-signed flag) from a DER certificate prefix
+fn parse_x509_validity(der: &[u8]) -> Option<CertificateInfo> {
+    // Certificate ::= SEQUENCE { tbsCertificate SEQUENCE { ... } ... }
+    let cert = Der::new(der).enter(0x30)?;
+    let mut tbs = Der::new(Der::new(cert).enter(0x30)?);
+
+    if tbs.peek_tag() == Some(0xa0) {
+        tbs.skip()?; // [0] version
+    }
+    tbs.skip()?; // serialNumber
+    tbs.skip()?; // signature
+    let issuer = tbs.element(0x30)?;
+
+    let mut validity = Der::new(tbs.enter(0x30)?);
+    let not_before = validity.time()?;
+    let not_after = validity.time()?;
+
+    let subject = tbs.element(0x30);
+    Some(CertificateInfo {
+        not_before,
+        not_after,
+        self_signed: subject.map(|s| s == issuer),
+    })
+}
+
+/// Bounds-checked big-endian reader
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(n)?;
+        let slice = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    /// Take up to `n` bytes (fewer if truncated)
+    fn take_up_to(&mut self, n: usize) -> &'a [u8] {
+        let end = self.pos.saturating_add(n).min(self.data.len());
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        slice
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Option<u32> {
+        self.take(3).map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+}
+
+/// Minimal DER walker tolerant of truncated containers
+struct Der<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Returns (tag, content start, content length)
+    fn header(&self) -> Option<(u8, usize, usize)> {
+        let tag = *self.data.get(self.pos)?;
+        let first = *self.data.get(self.pos + 1)? as usize;
+        if first < 0x80 {
+            return Some((tag, self.pos + 2, first));
+        }
+        let n = first & 0x7f;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let bytes = self.data.get(self.pos + 2..self.pos + 2 + n)?;
+        let len = bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        Some((tag, self.pos + 2 + n, len))
+    }
+
+    /// Content of a constructed element, possibly truncated
+    fn enter(&mut self, tag: u8) -> Option<&'a [u8]> {
+        let (t, start, len) = self.header()?;
+        if t != tag {
+            return None;
+        }
+        let end = start.saturating_add(len).min(self.data.len());
+        self.pos = end;
+        self.data.get(start..end)
+    }
+
+    /// Complete encoding of an element
+    fn element(&mut self, tag: u8) -> Option<&'a [u8]> {
+        let (t, start, len) = self.header()?;
+        if t != tag {
+            return None;
+        }
+        let element = self.data.get(self.pos..start + len)?;
+        self.pos = start + len;
+        Some(element)
+    }
+
+    fn skip(&mut self) -> Option<()> {
+        let (_, start, len) = self.header()?;
+        if start + len > self.data.len() {
+            return None;
+        }
+        self.pos = start + len;
+        Some(())
+    }
+
+    /// UTCTime or GeneralizedTime as unix seconds
+    fn time(&mut self) -> Option<i64> {
+        let (tag, start, len) = self.header()?;
+        let text = std::str::from_utf8(self.data.get(start..start + len)?).ok()?;
+        self.pos = start + len;
+
+        let digits = text.strip_suffix('Z')?;
+        let (year, rest) = match tag {
+            0x17 => {
+                let yy: i64 = digits.get(0..2)?.parse().ok()?;
+                (if yy >= 50 { 1900 + yy } else { 2000 + yy }, digits.get(2..)?)
+            }
+            0x18 => (digits.get(0..4)?.parse().ok()?, digits.get(4..)?),
+            _ => return None,
+        };
+
+        let field = |i: usize| -> Option<i64> { rest.get(i..i + 2)?.parse().ok() };
+        let (month, day) = (field(0)?, field(2)?);
+        let (hour, minute, second) = (field(4)?, field(6)?, field(8).unwrap_or(0));
+
+        Some(days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second)
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ClientHello with GREASE, SNI, ALPN h2, sig algs and supported_versions
+    fn client_hello() -> Vec<u8> {
+        let mut exts = Vec::new();
+        // GREASE extension
+        exts.extend_from_slice(&[0x1a, 0x1a, 0x00, 0x00]);
+        // SNI: example.com
+        let name = b"example.com";
+        exts.extend_from_slice(&[0x00, 0x00]);
+        exts.extend_from_slice(&((name.len() + 5) as u16).to_be_bytes());
+        exts.extend_from_slice(&((name.len() + 3) as u16).to_be_bytes());
+        exts.push(0);
+        exts.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        exts.extend_from_slice(name);
+        // ALPN: h2, http/1.1
+        exts.extend_from_slice(&[0x00, 0x10, 0x00, 0x0e, 0x00, 0x0c, 0x02, b'h', b'2', 0x08]);
+        exts.extend_from_slice(b"http/1.1");
+        // signature_algorithms
+        exts.extend_from_slice(&[0x00, 0x0d, 0x00, 0x06, 0x00, 0x04, 0x04, 0x03, 0x08, 0x04]);
+        // supported_versions: GREASE, 1.3, 1.2
+        exts.extend_from_slice(&[0x00, 0x2b, 0x00, 0x07, 0x06, 0x3a, 0x3a, 0x03, 0x04, 0x03, 0x03]);
+
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0u8; 32]);
+        body.push(0); // session id
+        body.extend_from_slice(&[0x00, 0x08, 0x2a, 0x2a, 0x13, 0x01, 0x13, 0x02, 0xc0, 0x2b]);
+        body.extend_from_slice(&[0x01, 0x00]); // compression
+        body.extend_from_slice(&(exts.len() as u16).to_be_bytes());
+        body.extend_from_slice(&exts);
+
+        let mut hs = vec![HS_CLIENT_HELLO];
+        hs.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        hs.extend_from_slice(&body);
+
+        let mut record = vec![TLS_HANDSHAKE, 0x03, 0x01];
+        record.extend_from_slice(&(hs.len() as u16).to_be_bytes());
+        record.extend_from_slice(&hs);
+        record
+    }
+
+    #[test]
+    fn test_client_hello_ja4() {
+        let hello = ClientHello::parse(&client_hello()).unwrap();
+        assert_eq!(hello.version, 0x0304);
+        assert_eq!(hello.cipher_suites, vec![0x1301, 0x1302, 0xc02b]);
+        assert_eq!(hello.sni.as_deref(), Some("example.com"));
+        assert_eq!(hello.alpn, vec!["h2", "http/1.1"]);
+
+        // a: TCP, TLS 1.3, SNI, 3 ciphers, 4 extensions, ALPN h2
+        // b: sha256("1301,1302,c02b")
+        // c: sha256("000d,002b_0403,0804")
+        assert_eq!(hello.ja4(), "t13d0304h2_5559582ccdc4_ef5f37ab036a");
+    }
+
+    #[test]
+    fn test_certificate_validity() {
+        // Minimal certificate: version, serial, alg, issuer, validity, subject
+        let name = [0x30, 0x03, 0x31, 0x01, 0x00];
+        let mut tbs_content = vec![0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01, 0x30, 0x00];
+        tbs_content.extend_from_slice(&name);
+        tbs_content.extend_from_slice(&[0x30, 0x1e, 0x17, 0x0d]);
+        tbs_content.extend_from_slice(b"240101000000Z");
+        tbs_content.extend_from_slice(&[0x17, 0x0d]);
+        tbs_content.extend_from_slice(b"250101000000Z");
+        tbs_content.extend_from_slice(&name);
+
+        let mut tbs = vec![0x30, tbs_content.len() as u8];
+        tbs.extend_from_slice(&tbs_content);
+        let mut cert = vec![0x30, tbs.len() as u8];
+        cert.extend_from_slice(&tbs);
+
+        let mut msg = vec![HS_CERTIFICATE];
+        msg.extend_from_slice(&((cert.len() + 6) as u32).to_be_bytes()[1..]);
+        msg.extend_from_slice(&((cert.len() + 3) as u32).to_be_bytes()[1..]);
+        msg.extend_from_slice(&(cert.len() as u32).to_be_bytes()[1..]);
+        msg.extend_from_slice(&cert);
+
+        let mut record = vec![TLS_HANDSHAKE, 0x03, 0x03];
+        record.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+        record.extend_from_slice(&msg);
+
+        let info = parse_certificate(&record).unwrap();
+        assert_eq!(info.not_before, 1_704_067_200);
+        assert_eq!(info.not_after, 1_735_689_600);
+        assert_eq!(info.self_signed, Some(true));
+    }
+
+    #[test]
+    fn test_splt_export() {
+        let mut extractor = EtaExtractor::new(EtaConfig {
+            splt_packets: 4,
+            ..Default::default()
+        });
+        let key = FlowKey::new(0x0a000001, 0x08080808, 50000, 443, 6);
+        let reply = key.reverse();
+
+        assert!(extractor.observe(&key, &client_hello(), 0x18, 1_000).is_none());
+        assert!(extractor.observe(&reply, &[0u8; 1200], 0x18, 21_000).is_none());
+        assert!(extractor.observe(&key, &[], 0x10, 22_000).is_none()); // bare ACK
+        assert!(extractor.observe(&key, &[0u8; 100], 0x18, 30_000).is_none());
+
+        let record = extractor.observe(&reply, &[0u8; 500], 0x18, 40_000).unwrap();
+        assert!(!record.complete);
+        assert_eq!(record.src_port, 50000);
+        assert_eq!(record.splt.len(), 4);
+        assert_eq!(record.splt[1].direction, Direction::Inbound);
+        assert_eq!(record.splt[1].iat_us, 20_000);
+        assert_eq!(record.sni.as_deref(), Some("example.com"));
+        assert!(record.ja4.unwrap().starts_with("t13d"));
+
+        let last = extractor.observe(&key, &[], TCP_FIN | 0x10, 50_000).unwrap();
+        assert!(last.complete);
+        assert_eq!(last.packets_out, 4);
+        assert_eq!(last.bytes_in, 1700);
+        assert_eq!(extractor.flow_count(), 0);
+    }
+}
@@ -553,7 +553,7 @@ pub enum FlowTableError {
 
 /// Get current timestamp in microseconds
 #[inline(always)]
-fn timestamp_micros() -> u64 {
+pub(crate) fn timestamp_micros() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
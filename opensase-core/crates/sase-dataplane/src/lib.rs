@@ -62,6 +62,8 @@ pub mod pipeline;
 pub mod buffer;
 pub mod stats;
 pub mod crypto;
+pub mod update;
+pub mod eta;
 
 #[cfg(feature = "af_xdp")]
 pub mod af_xdp;
@@ -73,6 +75,8 @@ pub use core::{FastPathEngine, EngineConfig};
 pub use flow::{FlowTable, FlowKey, FlowState};
 pub use pipeline::{Pipeline, Stage};
 pub use buffer::{PacketBuffer, BufferPool};
+pub use update::{ConfigManager, CoreConfig, DataplaneConfig, UpdateMetrics};
+pub use eta::{EtaExtractor, EtaRecord, EtaStage};
 
 /// Batch size for packet processing
 pub const BATCH_SIZE: usize = 64;
@@ -0,0 +1,643 @@
+//! Graceful Configuration Updates
+//!
+//! Make-before-break replacement of ACLs and tunnel SAs without pausing
+//! the fast path.
+//!
+//! # Design
+//!
+//! - New classification structures are compiled off the fast path,
+//!   alongside the live ones
+//! - Publishing swaps a single `Arc` and bumps a generation counter
+//! - Each core picks up the new snapshot at a batch boundary and
+//!   acknowledges it through its per-core epoch, so a batch never sees
+//!   a mix of old and new config
+//! - Replaced tunnel SAs keep decrypting until the peer is seen using the
+//!   new SA (or the drain timeout expires)
+
+use crate::crypto::{CryptoAlgorithm, CryptoContext};
+use crate::flow::{FlowKey, FlowVerdict};
+use crossbeam::utils::CachePadded;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Number of completed updates kept for inspection
+const UPDATE_HISTORY: usize = 32;
+
+/// Desired dataplane configuration, as pushed by the control plane
+#[derive(Debug, Clone, Default)]
+pub struct DataplaneConfig {
+    /// ACL rules, evaluated by ascending priority
+    pub acl: Vec<AclRule>,
+    /// Verdict when no rule matches
+    pub default_verdict: FlowVerdict,
+    /// Tunnels and their current SA
+    pub tunnels: Vec<TunnelSa>,
+}
+
+/// ACL rule
+#[derive(Debug, Clone)]
+pub struct AclRule {
+    /// Rule ID (reported on match)
+    pub id: u32,
+    /// Lower value wins
+    pub priority: u32,
+    /// Source prefix (IPv4 as u32)
+    pub src: (u32, u8),
+    /// Destination prefix
+    pub dst: (u32, u8),
+    /// Inclusive destination port range
+    pub dst_ports: (u16, u16),
+    /// IP protocol, `None` for any
+    pub protocol: Option<u8>,
+    /// Verdict on match
+    pub verdict: FlowVerdict,
+}
+
+impl AclRule {
+    /// Rule matching any traffic
+    pub fn any(id: u32, priority: u32, verdict: FlowVerdict) -> Self {
+        Self { id, priority, src: (0, 0), dst: (0, 0), dst_ports: (0, u16::MAX), protocol: None, verdict }
+    }
+}
+
+/// Tunnel with its security association
+#[derive(Debug, Clone)]
+pub struct TunnelSa {
+    /// Tunnel ID
+    pub tunnel_id: u32,
+    /// Security parameter index of the SA
+    pub spi: u32,
+    /// Cipher
+    pub algorithm: CryptoAlgorithm,
+    /// SA key
+    pub key: [u8; 32],
+}
+
+/// Compiled ACL lookup structure
+#[derive(Debug, Default)]
+pub struct AclClassifier {
+    rules: Vec<CompiledRule>,
+    default_verdict: FlowVerdict,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    id: u32,
+    src_net: u32,
+    src_mask: u32,
+    dst_net: u32,
+    dst_mask: u32,
+    port_lo: u16,
+    port_hi: u16,
+    protocol: Option<u8>,
+    verdict: FlowVerdict,
+}
+
+impl AclClassifier {
+    /// Validate and compile rules
+    pub fn compile(rules: &[AclRule], default_verdict: FlowVerdict) -> Result<Self, UpdateError> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        let mut sorted: Vec<&AclRule> = rules.iter().collect();
+        sorted.sort_by_key(|r| (r.priority, r.id));
+
+        for rule in sorted {
+            if rule.src.1 > 32 || rule.dst.1 > 32 {
+                return Err(UpdateError::InvalidRule(rule.id, "prefix length over 32".into()));
+            }
+            if rule.dst_ports.0 > rule.dst_ports.1 {
+                return Err(UpdateError::InvalidRule(rule.id, "empty port range".into()));
+            }
+            let src_mask = prefix_mask(rule.src.1);
+            let dst_mask = prefix_mask(rule.dst.1);
+            compiled.push(CompiledRule {
+                id: rule.id,
+                src_net: rule.src.0 & src_mask,
+                src_mask,
+                dst_net: rule.dst.0 & dst_mask,
+                dst_mask,
+                port_lo: rule.dst_ports.0,
+                port_hi: rule.dst_ports.1,
+                protocol: rule.protocol,
+                verdict: rule.verdict,
+            });
+        }
+
+        Ok(Self { rules: compiled, default_verdict })
+    }
+
+    /// First matching rule ID and its verdict
+    #[inline]
+    pub fn classify(&self, key: &FlowKey) -> (Option<u32>, FlowVerdict) {
+        for rule in &self.rules {
+            if key.src_ip & rule.src_mask == rule.src_net
+                && key.dst_ip & rule.dst_mask == rule.dst_net
+                && key.dst_port >= rule.port_lo
+                && key.dst_port <= rule.port_hi
+                && rule.protocol.is_none_or(|p| p == key.protocol)
+            {
+                return (Some(rule.id), rule.verdict);
+            }
+        }
+        (None, self.default_verdict)
+    }
+
+    /// Number of compiled rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether no rules are installed
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+#[inline]
+fn prefix_mask(len: u8) -> u32 {
+    if len == 0 { 0 } else { u32::MAX << (32 - len as u32) }
+}
+
+/// Live SA shared between snapshots and the drain list
+#[derive(Debug)]
+pub struct SaState {
+    /// Security parameter index
+    pub spi: u32,
+    /// Crypto context for this SA
+    pub context: CryptoContext,
+    /// Inbound packets decrypted with this SA
+    pub rx_packets: AtomicU64,
+}
+
+impl SaState {
+    fn new(sa: &TunnelSa) -> Self {
+        Self {
+            spi: sa.spi,
+            context: CryptoContext::new(sa.tunnel_id, sa.algorithm, sa.key),
+            rx_packets: AtomicU64::new(0),
+        }
+    }
+
+    fn same_as(&self, sa: &TunnelSa, key: &[u8; 32]) -> bool {
+        self.spi == sa.spi && self.context.algorithm == sa.algorithm && *key == sa.key
+    }
+}
+
+/// Immutable, fully compiled configuration seen by the fast path
+#[derive(Debug, Default)]
+pub struct ConfigSnapshot {
+    /// Generation this snapshot was published as
+    pub version: u64,
+    /// Compiled ACL
+    pub acl: AclClassifier,
+    tunnels: HashMap<u32, Arc<SaState>>,
+    keys: HashMap<u32, [u8; 32]>,
+}
+
+impl ConfigSnapshot {
+    /// Current (outbound) SA for a tunnel
+    #[inline]
+    pub fn outbound_sa(&self, tunnel_id: u32) -> Option<&Arc<SaState>> {
+        self.tunnels.get(&tunnel_id)
+    }
+}
+
+/// SA replaced by an update, kept for inbound traffic until migration
+#[derive(Debug)]
+struct DrainingSa {
+    tunnel_id: u32,
+    old: Arc<SaState>,
+    replacement: Option<Arc<SaState>>,
+    old_rx_baseline: u64,
+    new_rx_baseline: u64,
+    version: u64,
+    since: Instant,
+}
+
+/// Per-update metrics
+#[derive(Debug, Clone)]
+pub struct UpdateMetrics {
+    /// Generation published by the update
+    pub version: u64,
+    /// Wall-clock publish time
+    pub published_at: SystemTime,
+    /// Time spent compiling the new structures
+    pub build_time: Duration,
+    /// Publish until every core acknowledged, once complete
+    pub swap_time: Option<Duration>,
+    /// Packets processed by cores still on an older generation
+    pub stale_packets: u64,
+    /// SAs put into draining by this update
+    pub sas_draining: usize,
+    /// Draining SAs retired so far
+    pub sas_retired: usize,
+    /// Inbound packets decrypted with draining SAs
+    pub old_sa_packets: u64,
+}
+
+/// Update handling parameters
+#[derive(Debug, Clone)]
+pub struct UpdateSettings {
+    /// Longest an old SA stays usable when the peer never migrates
+    pub max_drain: Duration,
+    /// Inbound packets on the new SA that confirm migration
+    pub confirm_packets: u64,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self { max_drain: Duration::from_secs(30), confirm_packets: 1 }
+    }
+}
+
+struct InFlight {
+    index: usize,
+    published: Instant,
+}
+
+/// Owner of the published config and per-core epochs
+pub struct ConfigManager {
+    settings: UpdateSettings,
+    current: RwLock<Arc<ConfigSnapshot>>,
+    generation: AtomicU64,
+    core_epochs: Vec<CachePadded<AtomicU64>>,
+    stale_packets: AtomicU64,
+    draining: RwLock<HashMap<u32, DrainingSa>>,
+    updates: Mutex<VecDeque<UpdateMetrics>>,
+    in_flight: Mutex<Option<InFlight>>,
+    apply_lock: Mutex<()>,
+}
+
+impl ConfigManager {
+    /// Create manager for `num_cores` workers with an empty config
+    pub fn new(num_cores: usize, settings: UpdateSettings) -> Arc<Self> {
+        Arc::new(Self {
+            settings,
+            current: RwLock::new(Arc::new(ConfigSnapshot::default())),
+            generation: AtomicU64::new(0),
+            core_epochs: (0..num_cores).map(|_| CachePadded::new(AtomicU64::new(0))).collect(),
+            stale_packets: AtomicU64::new(0),
+            draining: RwLock::new(HashMap::new()),
+            updates: Mutex::new(VecDeque::with_capacity(UPDATE_HISTORY)),
+            in_flight: Mutex::new(None),
+            apply_lock: Mutex::new(()),
+        })
+    }
+
+    /// Latest published generation
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Latest published snapshot
+    pub fn snapshot(&self) -> Arc<ConfigSnapshot> {
+        self.current.read().clone()
+    }
+
+    /// Fast-path handle for one core
+    pub fn core_handle(self: &Arc<Self>, core_id: usize) -> CoreConfig {
+        assert!(core_id < self.core_epochs.len(), "core {} out of range", core_id);
+        let snapshot = self.snapshot();
+        self.core_epochs[core_id].store(snapshot.version, Ordering::Release);
+        CoreConfig { core_id, manager: self.clone(), snapshot }
+    }
+
+    /// Compile `config` next to the live snapshot and publish it
+    ///
+    /// Returns the new generation. Cores switch at their next batch; use
+    /// [`wait_for_swap`](Self::wait_for_swap) to block until they have.
+    pub fn apply(&self, config: &DataplaneConfig) -> Result<u64, UpdateError> {
+        let _guard = self.apply_lock.lock();
+        let started = Instant::now();
+        let old = self.snapshot();
+        let version = old.version + 1;
+
+        let acl = AclClassifier::compile(&config.acl, config.default_verdict)?;
+        let mut tunnels = HashMap::with_capacity(config.tunnels.len());
+        let mut keys = HashMap::with_capacity(config.tunnels.len());
+        let mut replaced = Vec::new();
+
+        for sa in &config.tunnels {
+            if tunnels.contains_key(&sa.tunnel_id) {
+                return Err(UpdateError::DuplicateTunnel(sa.tunnel_id));
+            }
+            let state = match (old.tunnels.get(&sa.tunnel_id), old.keys.get(&sa.tunnel_id)) {
+                (Some(live), Some(key)) if live.same_as(sa, key) => live.clone(),
+                (Some(live), _) => {
+                    let fresh = Arc::new(SaState::new(sa));
+                    replaced.push((sa.tunnel_id, live.clone(), Some(fresh.clone())));
+                    fresh
+                }
+                (None, _) => Arc::new(SaState::new(sa)),
+            };
+            tunnels.insert(sa.tunnel_id, state);
+            keys.insert(sa.tunnel_id, sa.key);
+        }
+        for (tunnel_id, live) in &old.tunnels {
+            if !tunnels.contains_key(tunnel_id) {
+                replaced.push((*tunnel_id, live.clone(), None));
+            }
+        }
+
+        let snapshot = Arc::new(ConfigSnapshot { version, acl, tunnels, keys });
+        let build_time = started.elapsed();
+
+        // Old SAs must be reachable before cores can stop finding them in
+        // the snapshot
+        let sas_draining = replaced.len();
+        {
+            let mut draining = self.draining.write();
+            for (tunnel_id, old_sa, replacement) in replaced {
+                draining.insert(tunnel_id, DrainingSa {
+                    tunnel_id,
+                    old_rx_baseline: old_sa.rx_packets.load(Ordering::Relaxed),
+                    new_rx_baseline: replacement.as_ref()
+                        .map_or(0, |sa| sa.rx_packets.load(Ordering::Relaxed)),
+                    old: old_sa,
+                    replacement,
+                    version,
+                    since: Instant::now(),
+                });
+            }
+        }
+
+        self.finish_swap();
+        self.stale_packets.store(0, Ordering::Relaxed);
+        *self.current.write() = snapshot;
+        self.generation.store(version, Ordering::Release);
+        let published = Instant::now();
+
+        // A newer generation supersedes any swap still in flight
+        let mut in_flight = self.in_flight.lock();
+        let mut updates = self.updates.lock();
+        if updates.len() == UPDATE_HISTORY {
+            updates.pop_front();
+        }
+        updates.push_back(UpdateMetrics {
+            version,
+            published_at: SystemTime::now(),
+            build_time,
+            swap_time: None,
+            stale_packets: 0,
+            sas_draining,
+            sas_retired: 0,
+            old_sa_packets: 0,
+        });
+        *in_flight = Some(InFlight { index: updates.len() - 1, published });
+        drop(updates);
+        drop(in_flight);
+
+        metrics::histogram!("dataplane_config_build_seconds").record(build_time.as_secs_f64());
+        tracing::info!(
+            "Published dataplane config v{} ({} rules, {} tunnels, {} SAs draining)",
+            version, config.acl.len(), config.tunnels.len(), sas_draining
+        );
+
+        Ok(version)
+    }
+
+    /// Oldest generation any core is still processing with
+    pub fn min_core_epoch(&self) -> u64 {
+        self.core_epochs.iter()
+            .map(|e| e.load(Ordering::Acquire))
+            .min()
+            .unwrap_or_else(|| self.generation())
+    }
+
+    /// Wait until every core runs the latest generation
+    ///
+    /// Returns the swap time, or `None` on timeout.
+    pub fn wait_for_swap(&self, timeout: Duration) -> Option<Duration> {
+        let target = self.generation();
+        let deadline = Instant::now() + timeout;
+        while self.min_core_epoch() < target {
+            if Instant::now() >= deadline {
+                return None;
+            }
+            std::thread::sleep(Duration::from_micros(50));
+        }
+        self.finish_swap();
+        self.updates.lock().iter()
+            .find(|u| u.version == target)
+            .and_then(|u| u.swap_time)
+    }
+
+    /// Record swap completion once all cores caught up
+    fn finish_swap(&self) {
+        let mut in_flight = self.in_flight.lock();
+        let Some(flight) = in_flight.as_ref() else { return };
+        let mut updates = self.updates.lock();
+        let Some(update) = updates.get_mut(flight.index) else {
+            *in_flight = None;
+            return;
+        };
+        update.stale_packets = self.stale_packets.load(Ordering::Relaxed);
+        if self.min_core_epoch() < update.version {
+            return;
+        }
+
+        let swap_time = flight.published.elapsed();
+        update.swap_time = Some(swap_time);
+        metrics::histogram!("dataplane_config_swap_seconds").record(swap_time.as_secs_f64());
+        metrics::counter!("dataplane_config_stale_packets").increment(update.stale_packets);
+        tracing::debug!("Config v{} live on all cores after {:?}", update.version, swap_time);
+        *in_flight = None;
+    }
+
+    /// Retire draining SAs whose peer migrated or whose drain expired
+    ///
+    /// Call periodically from the control thread. Returns the number of
+    /// SAs retired.
+    pub fn maintain(&self) -> usize {
+        self.finish_swap();
+
+        let mut retired = Vec::new();
+        self.draining.write().retain(|_, sa| {
+            let confirmed = sa.replacement.as_ref().is_some_and(|new| {
+                new.rx_packets.load(Ordering::Relaxed) - sa.new_rx_baseline >= self.settings.confirm_packets
+            });
+            if confirmed || sa.since.elapsed() >= self.settings.max_drain {
+                let old_packets = sa.old.rx_packets.load(Ordering::Relaxed) - sa.old_rx_baseline;
+                retired.push((sa.version, sa.tunnel_id, sa.old.spi, old_packets, confirmed));
+                false
+            } else {
+                true
+            }
+        });
+
+        if retired.is_empty() {
+            return 0;
+        }
+        let mut updates = self.updates.lock();
+        for (version, tunnel_id, spi, old_packets, confirmed) in &retired {
+            if let Some(update) = updates.iter_mut().find(|u| u.version == *version) {
+                update.sas_retired += 1;
+                update.old_sa_packets += old_packets;
+            }
+            metrics::counter!("dataplane_config_old_sa_packets").increment(*old_packets);
+            if *confirmed {
+                tracing::debug!("Retired SA {:#x} on tunnel {} after peer migrated", spi, tunnel_id);
+            } else {
+                tracing::warn!("Retired SA {:#x} on tunnel {} after drain timeout", spi, tunnel_id);
+            }
+        }
+        retired.len()
+    }
+
+    /// SAs still accepted for inbound traffic after being replaced
+    pub fn draining_count(&self) -> usize {
+        self.draining.read().len()
+    }
+
+    /// Metrics for recent updates, oldest first
+    pub fn update_metrics(&self) -> Vec<UpdateMetrics> {
+        self.finish_swap();
+        self.updates.lock().iter().cloned().collect()
+    }
+
+    fn draining_sa(&self, tunnel_id: u32, spi: u32) -> Option<Arc<SaState>> {
+        self.draining.read().get(&tunnel_id)
+            .filter(|sa| sa.old.spi == spi)
+            .map(|sa| sa.old.clone())
+    }
+}
+
+/// Per-core view of the dataplane config
+///
+/// Held by a worker; refreshed at batch boundaries only.
+pub struct CoreConfig {
+    core_id: usize,
+    manager: Arc<ConfigManager>,
+    snapshot: Arc<ConfigSnapshot>,
+}
+
+impl CoreConfig {
+    /// Pick up a newer snapshot, if any, and acknowledge it
+    #[inline]
+    pub fn begin_batch(&mut self) -> &ConfigSnapshot {
+        if self.manager.generation.load(Ordering::Acquire) != self.snapshot.version {
+            self.snapshot = self.manager.snapshot();
+            self.manager.core_epochs[self.core_id].store(self.snapshot.version, Ordering::Release);
+        }
+        &self.snapshot
+    }
+
+    /// Account a processed batch; counts it as stale if a newer
+    /// generation was published while it ran
+    #[inline]
+    pub fn end_batch(&self, packets: u64) {
+        if packets > 0 && self.manager.generation.load(Ordering::Relaxed) != self.snapshot.version {
+            self.manager.stale_packets.fetch_add(packets, Ordering::Relaxed);
+        }
+    }
+
+    /// Generation this core is running
+    pub fn version(&self) -> u64 {
+        self.snapshot.version
+    }
+
+    /// Classify a flow against this core's ACL
+    #[inline]
+    pub fn classify(&self, key: &FlowKey) -> FlowVerdict {
+        self.snapshot.acl.classify(key).1
+    }
+
+    /// Outbound SA for a tunnel
+    #[inline]
+    pub fn outbound_sa(&self, tunnel_id: u32) -> Option<&Arc<SaState>> {
+        self.snapshot.outbound_sa(tunnel_id)
+    }
+
+    /// SA for an inbound packet, falling back to draining SAs
+    ///
+    /// Counts the packet against the SA so migration can be confirmed.
+    #[inline]
+    pub fn inbound_sa(&self, tunnel_id: u32, spi: u32) -> Option<Arc<SaState>> {
+        let sa = match self.snapshot.tunnels.get(&tunnel_id) {
+            Some(sa) if sa.spi == spi => Some(sa.clone()),
+            _ => self.manager.draining_sa(tunnel_id, spi),
+        };
+        if let Some(sa) = &sa {
+            sa.rx_packets.fetch_add(1, Ordering::Relaxed);
+        }
+        sa
+    }
+}
+
+/// Configuration update errors
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    /// Rule failed validation
+    #[error("invalid ACL rule {0}: {1}")]
+    InvalidRule(u32, String),
+
+    /// Same tunnel listed twice
+    #[error("duplicate tunnel {0}")]
+    DuplicateTunnel(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sa(tunnel_id: u32, spi: u32) -> TunnelSa {
+        TunnelSa { tunnel_id, spi, algorithm: CryptoAlgorithm::Null, key: [spi as u8; 32] }
+    }
+
+    #[test]
+    fn test_acl_swap_per_core() {
+        let manager = ConfigManager::new(2, UpdateSettings::default());
+        let mut core0 = manager.core_handle(0);
+        let mut core1 = manager.core_handle(1);
+        let key = FlowKey::new(0x0A000001, 0x0A000002, 40000, 443, 6);
+
+        let mut config = DataplaneConfig::default();
+        config.acl.push(AclRule { dst_ports: (443, 443), protocol: Some(6), ..AclRule::any(1, 10, FlowVerdict::Drop) });
+        let version = manager.apply(&config).unwrap();
+
+        // Core 1 has not started a batch yet: old config, stale packets
+        assert_eq!(core0.begin_batch().acl.classify(&key), (Some(1), FlowVerdict::Drop));
+        assert_eq!(core1.classify(&key), FlowVerdict::Allow);
+        core1.end_batch(5);
+        assert_eq!(manager.wait_for_swap(Duration::from_millis(1)), None);
+
+        core1.begin_batch();
+        assert!(manager.wait_for_swap(Duration::from_millis(100)).is_some());
+        let metrics = manager.update_metrics();
+        assert_eq!(metrics.last().unwrap().version, version);
+        assert_eq!(metrics.last().unwrap().stale_packets, 5);
+    }
+
+    #[test]
+    fn test_sa_make_before_break() {
+        let manager = ConfigManager::new(1, UpdateSettings::default());
+        let mut core = manager.core_handle(0);
+        manager.apply(&DataplaneConfig { tunnels: vec![sa(7, 0x100)], ..Default::default() }).unwrap();
+        core.begin_batch();
+
+        // Rekey: old SA still decrypts until the peer uses the new one
+        manager.apply(&DataplaneConfig { tunnels: vec![sa(7, 0x200)], ..Default::default() }).unwrap();
+        core.begin_batch();
+        assert_eq!(core.outbound_sa(7).unwrap().spi, 0x200);
+        assert!(core.inbound_sa(7, 0x100).is_some());
+        assert_eq!(manager.maintain(), 0);
+
+        assert!(core.inbound_sa(7, 0x200).is_some());
+        assert_eq!(manager.maintain(), 1);
+        assert!(core.inbound_sa(7, 0x100).is_none());
+
+        let update = manager.update_metrics().pop().unwrap();
+        assert_eq!((update.sas_draining, update.sas_retired, update.old_sa_packets), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_invalid_config_keeps_live_snapshot() {
+        let manager = ConfigManager::new(1, UpdateSettings::default());
+        let mut config = DataplaneConfig::default();
+        config.acl.push(AclRule { dst_ports: (10, 1), ..AclRule::any(3, 1, FlowVerdict::Drop) });
+        assert!(manager.apply(&config).is_err());
+        assert_eq!(manager.generation(), 0);
+    }
+}
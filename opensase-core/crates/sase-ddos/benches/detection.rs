@@ -0,0 +1,44 @@
+//! Detection Benchmarks
+//!
+//! `AttackDetector::analyze` runs once per traffic sample, so it has to
+//! stay under the 100µs detection budget with many destinations tracked.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sase_ddos::detector::AttackDetector;
+use sase_ddos::{DetectionConfig, Protocol, TrafficSample};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Instant;
+
+fn sample(n: u32) -> TrafficSample {
+    TrafficSample {
+        timestamp: Instant::now(),
+        source: IpAddr::V4(Ipv4Addr::from(0xC633_6400 | (n & 0xFF))),
+        destination: IpAddr::V4(Ipv4Addr::from(0x0A00_0000 | (n % 10_000))),
+        protocol: Protocol::Udp,
+        src_port: 40_000 + (n % 1_000) as u16,
+        dst_port: 53,
+        packet_size: 512,
+        tcp_flags: None,
+        pps: 1_000,
+        bps: 4_096_000,
+    }
+}
+
+fn bench_analyze(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let detector = AttackDetector::new(DetectionConfig::default());
+
+    // Track 10k destinations before measuring
+    for n in 0..10_000 {
+        runtime.block_on(detector.analyze(&sample(n), None));
+    }
+
+    let mut n = 0u32;
+    c.bench_function("analyze", |b| b.iter(|| {
+        n = n.wrapping_add(1);
+        runtime.block_on(detector.analyze(black_box(&sample(n)), None))
+    }));
+}
+
+criterion_group!(benches, bench_analyze);
+criterion_main!(benches);
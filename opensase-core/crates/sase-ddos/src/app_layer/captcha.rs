@@ -0,0 +1,186 @@
+//! CAPTCHA Escalation
+//!
+//! Clients that keep failing or ignoring the proof-of-work page are
+//! escalated to a CAPTCHA from a hosted provider (Turnstile, hCaptcha or
+//! reCAPTCHA) before they are blocked. The page is a plain GET form, so the
+//! answer reaches the gateway the same way a proof-of-work answer does.
+//!
+//! Checking the provider's response needs a network round trip, so
+//! [`super::ChallengeService::inspect`] hands submissions back as
+//! [`super::Diversion::VerifyCaptcha`] and the gateway finishes them with
+//! [`super::ChallengeService::verify_captcha`].
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    Turnstile,
+    HCaptcha,
+    ReCaptcha,
+}
+
+impl CaptchaProvider {
+    pub fn script_url(self) -> &'static str {
+        match self {
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/api.js",
+            Self::HCaptcha => "https://js.hcaptcha.com/1/api.js",
+            Self::ReCaptcha => "https://www.google.com/recaptcha/api.js",
+        }
+    }
+
+    pub fn verify_url(self) -> &'static str {
+        match self {
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            Self::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            Self::ReCaptcha => "https://www.google.com/recaptcha/api/siteverify",
+        }
+    }
+
+    /// Element class the provider's script renders its widget into
+    pub fn widget_class(self) -> &'static str {
+        match self {
+            Self::Turnstile => "cf-turnstile",
+            Self::HCaptcha => "h-captcha",
+            Self::ReCaptcha => "g-recaptcha",
+        }
+    }
+
+    /// Form field the widget puts its response in
+    pub fn response_field(self) -> &'static str {
+        match self {
+            Self::Turnstile => "cf-turnstile-response",
+            Self::HCaptcha => "h-captcha-response",
+            Self::ReCaptcha => "g-recaptcha-response",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptchaConfig {
+    pub provider: CaptchaProvider,
+    pub site_key: String,
+    /// Wrong proof-of-work answers before a client gets a CAPTCHA instead
+    pub after_failures: u32,
+    /// Unanswered proof-of-work pages before a client gets a CAPTCHA instead
+    pub after_unanswered: u32,
+    /// Path the CAPTCHA form submits to
+    pub verify_path: String,
+}
+
+impl CaptchaConfig {
+    pub fn new(provider: CaptchaProvider, site_key: impl Into<String>) -> Self {
+        Self {
+            provider,
+            site_key: site_key.into(),
+            after_failures: 2,
+            after_unanswered: 5,
+            verify_path: "/.well-known/osddos/captcha".to_string(),
+        }
+    }
+}
+
+/// A CAPTCHA answer whose token checked out locally and now needs the
+/// provider's verdict
+#[derive(Debug, Clone)]
+pub struct CaptchaSubmission {
+    pub client_ip: IpAddr,
+    /// Provider response from the widget
+    pub response: String,
+    pub return_to: String,
+}
+
+/// Asks the provider whether a widget response is genuine
+#[async_trait::async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    /// `Err` means the provider could not be asked, not that the client
+    /// failed
+    async fn verify(&self, response: &str, client_ip: IpAddr) -> Result<bool, String>;
+}
+
+/// Verifier for the providers' shared `siteverify` API
+pub struct SiteVerifier {
+    provider: CaptchaProvider,
+    secret: String,
+    client: reqwest::Client,
+}
+
+impl SiteVerifier {
+    pub fn new(provider: CaptchaProvider, secret: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+        Self { provider, secret: secret.into(), client }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+#[async_trait::async_trait]
+impl CaptchaVerifier for SiteVerifier {
+    async fn verify(&self, response: &str, client_ip: IpAddr) -> Result<bool, String> {
+        let remote_ip = client_ip.to_string();
+        let reply = self.client
+            .post(self.provider.verify_url())
+            .form(&[
+                ("secret", self.secret.as_str()),
+                ("response", response),
+                ("remoteip", remote_ip.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !reply.status().is_success() {
+            return Err(format!("siteverify returned {}", reply.status()));
+        }
+        let verdict: SiteVerifyResponse = reply.json().await.map_err(|e| e.to_string())?;
+        Ok(verdict.success)
+    }
+}
+
+pub(crate) fn render_page(config: &CaptchaConfig, token: &str, return_to: &str) -> String {
+    CAPTCHA_PAGE
+        .replace("{script}", config.provider.script_url())
+        .replace("{widget}", config.provider.widget_class())
+        .replace("{site_key}", &html_escape(&config.site_key))
+        .replace("{verify}", &html_escape(&config.verify_path))
+        .replace("{token}", token)
+        .replace("{return}", &html_escape(return_to))
+}
+
+fn html_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+const CAPTCHA_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Security Check</title>
+    <meta charset="utf-8">
+    <meta name="robots" content="noindex">
+    <script src="{script}" async defer></script>
+    <style>
+        body { font-family: sans-serif; text-align: center; padding: 50px; }
+        form { display: inline-block; margin-top: 20px; }
+        button { margin-top: 15px; padding: 8px 24px; }
+    </style>
+</head>
+<body>
+    <h1>One more step</h1>
+    <p>Please confirm you are not a robot to continue.</p>
+    <form method="GET" action="{verify}">
+        <input type="hidden" name="token" value="{token}">
+        <input type="hidden" name="return" value="{return}">
+        <div class="{widget}" data-sitekey="{site_key}"></div>
+        <button type="submit">Continue</button>
+    </form>
+</body>
+</html>"#;
@@ -0,0 +1,1065 @@
+//! L7 Flood Challenges
+//!
+//! Diverts suspect HTTP clients to a JavaScript proof-of-work page before
+//! their requests reach the origin. The page asks the browser to find a
+//! nonce whose SHA-256 over the challenge token has a number of leading zero
+//! bits, then sends it to the verify path. A correct answer earns a pass
+//! cookie bound to the client address; requests carrying it re-enter without
+//! another challenge until it expires or exceeds its request budget.
+//!
+//! Tokens and pass cookies are HMAC-signed, so nothing is stored per
+//! challenge except spent tokens (to refuse replays). Clients that keep
+//! failing, or keep ignoring the page, are escalated to a CAPTCHA when one
+//! is configured (see [`super::captcha`]) and blocked for a while if that
+//! fails too.
+//!
+//! Difficulty tunes itself from solve rates: when nearly every challenged
+//! client solves, the attack is likely driven by real browsers and the
+//! work goes up; when almost none do, the page is already filtering the
+//! flood and the work comes down to spare slow legitimate devices.
+//!
+//! The L7 gateway calls [`ChallengeService::inspect`] for every request
+//! and either forwards it, returns the response it is given, or blocks.
+//! CAPTCHA answers come back as [`Diversion::VerifyCaptcha`] and are
+//! finished with [`ChallengeService::verify_captcha`].
+
+use super::captcha::{self, CaptchaConfig, CaptchaSubmission, CaptchaVerifier};
+use super::HttpRequest;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone)]
+pub struct ChallengeConfig {
+    /// Leading zero bits required at start
+    pub difficulty: u8,
+    /// Bounds for automatic tuning
+    pub min_difficulty: u8,
+    pub max_difficulty: u8,
+    /// How long a challenge can be answered
+    pub challenge_ttl: Duration,
+    /// How long a pass cookie lets a client re-enter
+    pub pass_ttl: Duration,
+    /// Requests per minute a pass cookie allows before re-challenge
+    pub pass_rate_limit: u32,
+    /// Wrong answers before a client is blocked
+    pub max_failures: u32,
+    /// Challenges a client may leave unanswered before it is blocked
+    pub max_unanswered: u32,
+    pub block_duration: Duration,
+    /// How long a flagged client stays diverted
+    pub divert_ttl: Duration,
+    pub cookie_name: String,
+    /// Path the challenge page submits its answer to
+    pub verify_path: String,
+    /// How often pass-cookie keys rotate; the previous key stays valid
+    pub secret_rotation: Duration,
+    pub tuning: TuningConfig,
+    /// Escalate persistent failures to a CAPTCHA before blocking
+    pub captcha: Option<CaptchaConfig>,
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self {
+            difficulty: 16,
+            min_difficulty: 12,
+            max_difficulty: 22,
+            challenge_ttl: Duration::from_secs(120),
+            pass_ttl: Duration::from_secs(1800),
+            pass_rate_limit: 600,
+            max_failures: 5,
+            max_unanswered: 20,
+            block_duration: Duration::from_secs(600),
+            divert_ttl: Duration::from_secs(900),
+            cookie_name: "__osddos".to_string(),
+            verify_path: "/.well-known/osddos/verify".to_string(),
+            secret_rotation: Duration::from_secs(3600),
+            tuning: TuningConfig::default(),
+            captcha: None,
+        }
+    }
+}
+
+/// Solve-rate driven difficulty tuning
+#[derive(Debug, Clone)]
+pub struct TuningConfig {
+    pub interval: Duration,
+    /// Challenges needed in an interval before the rate is trusted
+    pub min_issued: u64,
+    /// Raise difficulty when more than this share solves
+    pub raise_above: f64,
+    /// Lower difficulty when less than this share solves
+    pub lower_below: f64,
+}
+
+impl Default for TuningConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            min_issued: 50,
+            raise_above: 0.9,
+            lower_below: 0.3,
+        }
+    }
+}
+
+/// What the gateway should do with a request
+#[derive(Debug, Clone)]
+pub enum Diversion {
+    /// Forward to the origin
+    Pass,
+    /// Answer with this response instead of forwarding
+    Respond(ChallengeResponse),
+    /// Refuse the request
+    Block { reason: String, retry_after_secs: u64 },
+    /// CAPTCHA answer awaiting the provider; finish with
+    /// [`ChallengeService::verify_captcha`]
+    VerifyCaptcha(CaptchaSubmission),
+}
+
+#[derive(Debug, Clone)]
+pub struct ChallengeResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Pass/fail history of one client address
+#[derive(Debug, Clone)]
+pub struct ClientRecord {
+    pub issued: u64,
+    pub passed: u64,
+    pub failed: u64,
+    /// Wrong answers since the last pass
+    pub consecutive_failures: u32,
+    /// Challenges issued since the last answer
+    pub unanswered: u32,
+    /// Escalated from proof-of-work to CAPTCHA until the next pass
+    pub captcha_required: bool,
+    pub flagged_until: Option<DateTime<Utc>>,
+    pub blocked_until: Option<DateTime<Utc>>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl ClientRecord {
+    fn new() -> Self {
+        Self {
+            issued: 0,
+            passed: 0,
+            failed: 0,
+            consecutive_failures: 0,
+            unanswered: 0,
+            captcha_required: false,
+            flagged_until: None,
+            blocked_until: None,
+            last_seen: Utc::now(),
+        }
+    }
+}
+
+/// Outcome of one tuning round
+#[derive(Debug, Clone)]
+pub struct TuningReport {
+    pub issued: u64,
+    pub passed: u64,
+    pub solve_rate: f64,
+    pub previous_difficulty: u8,
+    pub difficulty: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeStats {
+    pub issued: u64,
+    pub passed: u64,
+    pub failed: u64,
+    pub blocked: u64,
+    pub passes_revoked: u64,
+    pub captchas_issued: u64,
+    pub captchas_passed: u64,
+    pub difficulty: u8,
+    pub tracked_clients: usize,
+}
+
+struct PassUsage {
+    window_start: i64,
+    count: u32,
+}
+
+struct Keys {
+    current: [u8; 32],
+    previous: Option<[u8; 32]>,
+}
+
+/// Challenge issuance, verification and pass tracking
+pub struct ChallengeService {
+    config: ChallengeConfig,
+    keys: parking_lot::RwLock<Keys>,
+    difficulty: AtomicU8,
+    /// Destinations under HTTP flood protection, until when (None: until released)
+    destinations: DashMap<IpAddr, Option<DateTime<Utc>>>,
+    hosts: DashMap<String, Option<DateTime<Utc>>>,
+    clients: DashMap<IpAddr, ClientRecord>,
+    /// Answered tokens by salt, kept until they expire
+    spent: DashMap<String, i64>,
+    /// Request counts per pass cookie id
+    passes: DashMap<String, PassUsage>,
+    issued: AtomicU64,
+    passed: AtomicU64,
+    failed: AtomicU64,
+    blocked: AtomicU64,
+    passes_revoked: AtomicU64,
+    captchas_issued: AtomicU64,
+    captchas_passed: AtomicU64,
+    captcha_verifier: Option<Arc<dyn CaptchaVerifier>>,
+    /// Counters for the current tuning interval
+    window_issued: AtomicU64,
+    window_passed: AtomicU64,
+}
+
+impl ChallengeService {
+    pub fn new(config: ChallengeConfig) -> Self {
+        let difficulty = config.difficulty.clamp(config.min_difficulty, config.max_difficulty);
+        Self {
+            config,
+            keys: parking_lot::RwLock::new(Keys { current: random_key(), previous: None }),
+            difficulty: AtomicU8::new(difficulty),
+            destinations: DashMap::new(),
+            hosts: DashMap::new(),
+            clients: DashMap::new(),
+            spent: DashMap::new(),
+            passes: DashMap::new(),
+            issued: AtomicU64::new(0),
+            passed: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+            passes_revoked: AtomicU64::new(0),
+            captchas_issued: AtomicU64::new(0),
+            captchas_passed: AtomicU64::new(0),
+            captcha_verifier: None,
+            window_issued: AtomicU64::new(0),
+            window_passed: AtomicU64::new(0),
+        }
+    }
+
+    /// Check CAPTCHA answers with this provider client
+    pub fn with_captcha_verifier(mut self, verifier: Arc<dyn CaptchaVerifier>) -> Self {
+        self.captcha_verifier = Some(verifier);
+        self
+    }
+
+    pub fn config(&self) -> &ChallengeConfig {
+        &self.config
+    }
+
+    pub fn difficulty(&self) -> u8 {
+        self.difficulty.load(Ordering::Relaxed)
+    }
+
+    // =========================================================================
+    // Diversion
+    // =========================================================================
+
+    /// Challenge every client of a destination, e.g. while an HTTP flood
+    /// against it is mitigated
+    pub fn protect_destination(&self, destination: IpAddr, ttl: Option<Duration>) {
+        self.destinations.insert(destination, ttl.map(expiry));
+        info!("L7 challenges enabled for {}", destination);
+    }
+
+    pub fn release_destination(&self, destination: &IpAddr) -> bool {
+        self.destinations.remove(destination).is_some()
+    }
+
+    /// Challenge every client of a virtual host
+    pub fn protect_host(&self, host: &str, ttl: Option<Duration>) {
+        self.hosts.insert(host.to_ascii_lowercase(), ttl.map(expiry));
+        info!("L7 challenges enabled for host {}", host);
+    }
+
+    pub fn release_host(&self, host: &str) -> bool {
+        self.hosts.remove(&host.to_ascii_lowercase()).is_some()
+    }
+
+    /// Challenge one client wherever it goes, e.g. after a bot signature
+    /// or rate-limit hit
+    pub fn divert_client(&self, ip: IpAddr) {
+        let mut client = self.clients.entry(ip).or_insert_with(ClientRecord::new);
+        client.flagged_until = Some(expiry(self.config.divert_ttl));
+    }
+
+    /// Decide what to do with a request. Requests to the verify path are
+    /// answered here.
+    pub fn inspect(&self, request: &HttpRequest) -> Diversion {
+        let now = Utc::now();
+        if let Some(retry_after) = self.blocked_for(request.client_ip, now) {
+            return Diversion::Block {
+                reason: "Too many failed challenges".to_string(),
+                retry_after_secs: retry_after,
+            };
+        }
+
+        let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        if path == self.config.verify_path {
+            return self.verify(request.client_ip, query);
+        }
+        if self.config.captcha.as_ref().is_some_and(|c| path == c.verify_path) {
+            return self.check_captcha(request.client_ip, query);
+        }
+
+        match self.check_pass(request, true) {
+            PassState::Valid => return Diversion::Pass,
+            PassState::Exhausted => self.divert_client(request.client_ip),
+            PassState::Missing => {}
+        }
+
+        if !self.suspect(request, now) {
+            return Diversion::Pass;
+        }
+        self.challenge(request)
+    }
+
+    /// Whether a request carries a valid pass cookie, without counting it
+    /// against the pass's request budget
+    pub fn has_pass(&self, request: &HttpRequest) -> bool {
+        matches!(self.check_pass(request, false), PassState::Valid)
+    }
+
+    fn suspect(&self, request: &HttpRequest, now: DateTime<Utc>) -> bool {
+        let active = |until: &Option<DateTime<Utc>>| until.map_or(true, |u| u > now);
+
+        let flagged = self.clients.get(&request.client_ip)
+            .and_then(|c| c.flagged_until)
+            .is_some_and(|until| until > now);
+        let destination = request.destination
+            .and_then(|d| self.destinations.get(&d).map(|u| active(&u)))
+            .unwrap_or(false);
+        let host = self.hosts.get(&request.host.to_ascii_lowercase())
+            .is_some_and(|u| active(&u));
+
+        flagged || destination || host
+    }
+
+    fn blocked_for(&self, ip: IpAddr, now: DateTime<Utc>) -> Option<u64> {
+        let until = self.clients.get(&ip)?.blocked_until?;
+        (until > now).then(|| (until - now).num_seconds().max(1) as u64)
+    }
+
+    // =========================================================================
+    // Challenges
+    // =========================================================================
+
+    fn challenge(&self, request: &HttpRequest) -> Diversion {
+        let difficulty = self.difficulty();
+        let issued_at = Utc::now().timestamp();
+        let salt = hex::encode(random_bytes::<8>());
+        let payload = format!("{}.{}.{}", issued_at, difficulty, salt);
+        let token = format!("{}.{}", payload, self.sign(&format!("chal|{}|{}", request.client_ip, payload)));
+
+        self.issued.fetch_add(1, Ordering::Relaxed);
+        self.window_issued.fetch_add(1, Ordering::Relaxed);
+        let (ignored, captcha_required) = {
+            let mut client = self.clients.entry(request.client_ip).or_insert_with(ClientRecord::new);
+            client.issued += 1;
+            client.unanswered += 1;
+            client.last_seen = Utc::now();
+            if self.config.captcha.as_ref().is_some_and(|c| client.unanswered > c.after_unanswered) {
+                client.captcha_required = true;
+            }
+            if client.unanswered > self.config.max_unanswered {
+                client.blocked_until = Some(expiry(self.config.block_duration));
+                (true, client.captcha_required)
+            } else {
+                (false, client.captcha_required)
+            }
+        };
+        if ignored {
+            self.blocked.fetch_add(1, Ordering::Relaxed);
+            warn!("Blocking {}: {} challenges left unanswered", request.client_ip, self.config.max_unanswered);
+            return Diversion::Block {
+                reason: "Challenges not answered".to_string(),
+                retry_after_secs: self.config.block_duration.as_secs(),
+            };
+        }
+
+        let return_to = if request.method.eq_ignore_ascii_case("GET") { request.path.as_str() } else { "/" };
+        if let (true, Some(captcha)) = (captcha_required, &self.config.captcha) {
+            return self.captcha_challenge(request.client_ip, captcha, return_to);
+        }
+        Diversion::Respond(ChallengeResponse {
+            status: 503,
+            headers: vec![
+                ("Content-Type".to_string(), "text/html; charset=utf-8".to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ],
+            body: self.challenge_page(&token, difficulty, return_to),
+        })
+    }
+
+    /// Check an answer submitted as `token=..&nonce=..&return=..`
+    fn verify(&self, ip: IpAddr, query: &str) -> Diversion {
+        let mut token = "";
+        let mut nonce = "";
+        let mut return_to = String::from("/");
+        for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            match key {
+                "token" => token = value,
+                "nonce" => nonce = value,
+                "return" => return_to = percent_decode(value),
+                _ => {}
+            }
+        }
+        // Only same-site paths, never another origin
+        if !return_to.starts_with('/') || return_to.starts_with("//") {
+            return_to = "/".to_string();
+        }
+
+        match self.check_answer(ip, token, nonce) {
+            Ok(()) => self.pass(ip, &return_to),
+            Err(reason) => {
+                debug!("Challenge failed for {}: {}", ip, reason);
+                self.fail(ip)
+            }
+        }
+    }
+
+    fn check_answer(&self, ip: IpAddr, token: &str, nonce: &str) -> Result<(), &'static str> {
+        let (payload, mac) = token.rsplit_once('.').ok_or("malformed token")?;
+        if !self.verify_mac(&format!("chal|{}|{}", ip, payload), mac) {
+            return Err("bad signature");
+        }
+        let mut parts = payload.split('.');
+        let issued_at: i64 = parts.next().and_then(|p| p.parse().ok()).ok_or("malformed token")?;
+        let difficulty: u32 = parts.next().and_then(|p| p.parse().ok()).ok_or("malformed token")?;
+        let salt = parts.next().ok_or("malformed token")?;
+
+        let expires_at = issued_at + self.config.challenge_ttl.as_secs() as i64;
+        if Utc::now().timestamp() > expires_at {
+            return Err("expired");
+        }
+        if nonce.is_empty() || !nonce.bytes().all(|b| b.is_ascii_digit()) {
+            return Err("malformed nonce");
+        }
+        let hash = Sha256::digest(format!("{}:{}", token, nonce).as_bytes());
+        if leading_zero_bits(&hash) < difficulty {
+            return Err("insufficient work");
+        }
+        if self.spent.insert(salt.to_string(), expires_at).is_some() {
+            return Err("replayed");
+        }
+        Ok(())
+    }
+
+    fn pass(&self, ip: IpAddr, return_to: &str) -> Diversion {
+        self.passed.fetch_add(1, Ordering::Relaxed);
+        self.window_passed.fetch_add(1, Ordering::Relaxed);
+        if let Some(mut client) = self.clients.get_mut(&ip) {
+            client.passed += 1;
+            client.consecutive_failures = 0;
+            client.unanswered = 0;
+            client.captcha_required = false;
+            client.flagged_until = None;
+        }
+
+        let expires_at = Utc::now().timestamp() + self.config.pass_ttl.as_secs() as i64;
+        let id = hex::encode(random_bytes::<8>());
+        let payload = format!("{}.{}", expires_at, id);
+        let cookie = format!("{}.{}", payload, self.sign(&format!("pass|{}|{}", ip, payload)));
+
+        Diversion::Respond(ChallengeResponse {
+            status: 302,
+            headers: vec![
+                ("Location".to_string(), return_to.to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+                (
+                    "Set-Cookie".to_string(),
+                    format!(
+                        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+                        self.config.cookie_name, cookie, self.config.pass_ttl.as_secs()
+                    ),
+                ),
+            ],
+            body: String::new(),
+        })
+    }
+
+    fn fail(&self, ip: IpAddr) -> Diversion {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        let blocked = {
+            let mut client = self.clients.entry(ip).or_insert_with(ClientRecord::new);
+            client.failed += 1;
+            client.consecutive_failures += 1;
+            client.unanswered = 0;
+            client.last_seen = Utc::now();
+            if self.config.captcha.as_ref().is_some_and(|c| client.consecutive_failures >= c.after_failures) {
+                client.captcha_required = true;
+            }
+            if client.consecutive_failures >= self.config.max_failures {
+                client.blocked_until = Some(expiry(self.config.block_duration));
+                true
+            } else {
+                false
+            }
+        };
+
+        if blocked {
+            self.blocked.fetch_add(1, Ordering::Relaxed);
+            warn!("Blocking {}: {} failed challenges", ip, self.config.max_failures);
+            return Diversion::Block {
+                reason: "Too many failed challenges".to_string(),
+                retry_after_secs: self.config.block_duration.as_secs(),
+            };
+        }
+        Diversion::Respond(ChallengeResponse {
+            status: 403,
+            headers: vec![("Cache-Control".to_string(), "no-store".to_string())],
+            body: "Challenge failed, reload the page to try again".to_string(),
+        })
+    }
+
+    // =========================================================================
+    // CAPTCHA
+    // =========================================================================
+
+    fn captcha_challenge(&self, ip: IpAddr, captcha: &CaptchaConfig, return_to: &str) -> Diversion {
+        let payload = format!("{}.{}", Utc::now().timestamp(), hex::encode(random_bytes::<8>()));
+        let token = format!("{}.{}", payload, self.sign(&format!("capt|{}|{}", ip, payload)));
+        self.captchas_issued.fetch_add(1, Ordering::Relaxed);
+
+        Diversion::Respond(ChallengeResponse {
+            status: 403,
+            headers: vec![
+                ("Content-Type".to_string(), "text/html; charset=utf-8".to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ],
+            body: captcha::render_page(captcha, &token, return_to),
+        })
+    }
+
+    /// Check the signed part of a CAPTCHA answer submitted as
+    /// `token=..&return=..&<provider response field>=..`
+    fn check_captcha(&self, ip: IpAddr, query: &str) -> Diversion {
+        let Some(captcha) = &self.config.captcha else {
+            return Diversion::Pass;
+        };
+        let mut token = String::new();
+        let mut response = String::new();
+        let mut return_to = String::from("/");
+        for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            match key {
+                "token" => token = percent_decode(value),
+                "return" => return_to = percent_decode(value),
+                key if key == captcha.provider.response_field() => response = percent_decode(value),
+                _ => {}
+            }
+        }
+        if !return_to.starts_with('/') || return_to.starts_with("//") {
+            return_to = "/".to_string();
+        }
+
+        let checked = (|| {
+            let (payload, mac) = token.rsplit_once('.').ok_or("malformed token")?;
+            if !self.verify_mac(&format!("capt|{}|{}", ip, payload), mac) {
+                return Err("bad signature");
+            }
+            let (issued_at, salt) = payload.split_once('.').ok_or("malformed token")?;
+            let issued_at: i64 = issued_at.parse().map_err(|_| "malformed token")?;
+            let expires_at = issued_at + self.config.challenge_ttl.as_secs() as i64;
+            if Utc::now().timestamp() > expires_at {
+                return Err("expired");
+            }
+            if response.is_empty() {
+                return Err("no provider response");
+            }
+            if self.spent.insert(format!("capt.{}", salt), expires_at).is_some() {
+                return Err("replayed");
+            }
+            Ok(())
+        })();
+
+        match checked {
+            Ok(()) => Diversion::VerifyCaptcha(CaptchaSubmission { client_ip: ip, response, return_to }),
+            Err(reason) => {
+                debug!("CAPTCHA rejected for {}: {}", ip, reason);
+                self.fail(ip)
+            }
+        }
+    }
+
+    /// Ask the provider about a CAPTCHA answer and pass or fail the client
+    pub async fn verify_captcha(&self, submission: CaptchaSubmission) -> Diversion {
+        let Some(verifier) = &self.captcha_verifier else {
+            warn!("CAPTCHA answered by {} but no verifier is configured", submission.client_ip);
+            return self.fail(submission.client_ip);
+        };
+
+        match verifier.verify(&submission.response, submission.client_ip).await {
+            Ok(true) => {
+                self.captchas_passed.fetch_add(1, Ordering::Relaxed);
+                self.pass(submission.client_ip, &submission.return_to)
+            }
+            Ok(false) => {
+                debug!("CAPTCHA failed for {}", submission.client_ip);
+                self.fail(submission.client_ip)
+            }
+            Err(e) => {
+                // A provider outage is not the client's fault
+                warn!("CAPTCHA provider unavailable: {}", e);
+                Diversion::Respond(ChallengeResponse {
+                    status: 503,
+                    headers: vec![
+                        ("Cache-Control".to_string(), "no-store".to_string()),
+                        ("Retry-After".to_string(), "5".to_string()),
+                    ],
+                    body: "Verification is temporarily unavailable, please try again".to_string(),
+                })
+            }
+        }
+    }
+
+    // =========================================================================
+    // Pass Cookies
+    // =========================================================================
+
+    fn check_pass(&self, request: &HttpRequest, count: bool) -> PassState {
+        let Some(cookie) = request.cookie.as_deref().and_then(|c| cookie_value(c, &self.config.cookie_name)) else {
+            return PassState::Missing;
+        };
+        let Some((payload, mac)) = cookie.rsplit_once('.') else {
+            return PassState::Missing;
+        };
+        if !self.verify_mac(&format!("pass|{}|{}", request.client_ip, payload), mac) {
+            return PassState::Missing;
+        }
+        let Some((expires_at, id)) = payload.split_once('.') else {
+            return PassState::Missing;
+        };
+        let now = Utc::now().timestamp();
+        if expires_at.parse::<i64>().map_or(true, |e| e < now) {
+            return PassState::Missing;
+        }
+        if !count {
+            return PassState::Valid;
+        }
+
+        let window = now - now % 60;
+        let mut usage = self.passes.entry(id.to_string())
+            .or_insert(PassUsage { window_start: window, count: 0 });
+        if usage.window_start != window {
+            usage.window_start = window;
+            usage.count = 0;
+        }
+        usage.count += 1;
+        if usage.count > self.config.pass_rate_limit {
+            if usage.count == self.config.pass_rate_limit + 1 {
+                self.passes_revoked.fetch_add(1, Ordering::Relaxed);
+                debug!("Pass of {} exceeded {} requests/min", request.client_ip, self.config.pass_rate_limit);
+            }
+            return PassState::Exhausted;
+        }
+        PassState::Valid
+    }
+
+    fn sign(&self, message: &str) -> String {
+        hex::encode(mac(&self.keys.read().current, message))
+    }
+
+    fn verify_mac(&self, message: &str, mac_hex: &str) -> bool {
+        let Ok(expected) = hex::decode(mac_hex) else { return false };
+        let keys = self.keys.read();
+        std::iter::once(&keys.current)
+            .chain(keys.previous.as_ref())
+            .any(|key| {
+                let mut m = HmacSha256::new_from_slice(key).expect("HMAC takes any key length");
+                m.update(message.as_bytes());
+                m.verify_slice(&expected).is_ok()
+            })
+    }
+
+    /// Start signing with a new key; the previous one stays valid for
+    /// tokens and cookies already issued
+    pub fn rotate_secret(&self) {
+        let mut keys = self.keys.write();
+        keys.previous = Some(keys.current);
+        keys.current = random_key();
+    }
+
+    // =========================================================================
+    // Tuning and Housekeeping
+    // =========================================================================
+
+    /// Adjust difficulty from the solve rate since the last round
+    pub fn tune(&self) -> TuningReport {
+        let tuning = &self.config.tuning;
+        let issued = self.window_issued.swap(0, Ordering::Relaxed);
+        let passed = self.window_passed.swap(0, Ordering::Relaxed);
+        let solve_rate = if issued > 0 { passed as f64 / issued as f64 } else { 0.0 };
+
+        let previous = self.difficulty();
+        let mut difficulty = previous;
+        if issued >= tuning.min_issued {
+            if solve_rate > tuning.raise_above {
+                difficulty = (previous + 1).min(self.config.max_difficulty);
+            } else if solve_rate < tuning.lower_below {
+                difficulty = previous.saturating_sub(1).max(self.config.min_difficulty);
+            }
+        } else if issued == 0 && previous != self.config.difficulty {
+            // Quiet again: drift back to the configured work
+            difficulty = if previous > self.config.difficulty { previous - 1 } else { previous + 1 };
+        }
+
+        if difficulty != previous {
+            self.difficulty.store(difficulty, Ordering::Relaxed);
+            info!(
+                "Challenge difficulty {} -> {} bits ({} of {} solved)",
+                previous, difficulty, passed, issued
+            );
+        }
+
+        TuningReport { issued, passed, solve_rate, previous_difficulty: previous, difficulty }
+    }
+
+    /// Forget expired protections, spent tokens, pass counters and idle
+    /// clients
+    pub fn prune(&self) {
+        let now = Utc::now();
+        let timestamp = now.timestamp();
+        self.destinations.retain(|_, until| until.map_or(true, |u| u > now));
+        self.hosts.retain(|_, until| until.map_or(true, |u| u > now));
+        self.spent.retain(|_, expires_at| *expires_at >= timestamp);
+        self.passes.retain(|_, usage| timestamp - usage.window_start < 60);
+
+        let idle = now - chrono::Duration::from_std(self.config.pass_ttl).unwrap_or_default();
+        self.clients.retain(|_, c| {
+            c.last_seen > idle
+                || c.blocked_until.is_some_and(|u| u > now)
+                || c.flagged_until.is_some_and(|u| u > now)
+        });
+    }
+
+    /// Tune, prune and rotate keys in the background
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.tuning.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            let mut last_rotation = tokio::time::Instant::now();
+            loop {
+                ticker.tick().await;
+                self.tune();
+                self.prune();
+                if last_rotation.elapsed() >= self.config.secret_rotation {
+                    self.rotate_secret();
+                    last_rotation = tokio::time::Instant::now();
+                }
+            }
+        })
+    }
+
+    pub fn client(&self, ip: &IpAddr) -> Option<ClientRecord> {
+        self.clients.get(ip).map(|c| c.clone())
+    }
+
+    /// Clear a client's history and any block on it
+    pub fn forgive(&self, ip: &IpAddr) -> bool {
+        self.clients.remove(ip).is_some()
+    }
+
+    pub fn stats(&self) -> ChallengeStats {
+        ChallengeStats {
+            issued: self.issued.load(Ordering::Relaxed),
+            passed: self.passed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            passes_revoked: self.passes_revoked.load(Ordering::Relaxed),
+            captchas_issued: self.captchas_issued.load(Ordering::Relaxed),
+            captchas_passed: self.captchas_passed.load(Ordering::Relaxed),
+            difficulty: self.difficulty(),
+            tracked_clients: self.clients.len(),
+        }
+    }
+
+    fn challenge_page(&self, token: &str, difficulty: u8, return_to: &str) -> String {
+        // A JSON string literal, with '<' escaped so the path cannot close the script
+        let return_to = serde_json::to_string(return_to)
+            .unwrap_or_else(|_| "\"/\"".to_string())
+            .replace('<', "\\u003c");
+        CHALLENGE_PAGE
+            .replace("{token}", token)
+            .replace("{difficulty}", &difficulty.to_string())
+            .replace("{verify}", &self.config.verify_path)
+            .replace("{return}", &return_to)
+    }
+}
+
+impl Default for ChallengeService {
+    fn default() -> Self {
+        Self::new(ChallengeConfig::default())
+    }
+}
+
+enum PassState {
+    Missing,
+    Valid,
+    /// Signed and live, but over its request budget
+    Exhausted,
+}
+
+fn expiry(ttl: Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(365))
+}
+
+fn mac(key: &[u8], message: &str) -> Vec<u8> {
+    let mut m = HmacSha256::new_from_slice(key).expect("HMAC takes any key length");
+    m.update(message.as_bytes());
+    m.finalize().into_bytes().to_vec()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn random_key() -> [u8; 32] {
+    random_bytes::<32>()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+    bits
+}
+
+fn cookie_value<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header.split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Solves in the browser with WebCrypto; the hash and bit test must match
+/// [`leading_zero_bits`]
+const CHALLENGE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Security Check</title>
+    <meta charset="utf-8">
+    <meta name="robots" content="noindex">
+    <style>
+        body { font-family: sans-serif; text-align: center; padding: 50px; }
+        .spinner { width: 50px; height: 50px; border: 5px solid #f3f3f3;
+                   border-top: 5px solid #3498db; border-radius: 50%;
+                   animation: spin 1s linear infinite; margin: 20px auto; }
+        @keyframes spin { 0% { transform: rotate(0deg); } 100% { transform: rotate(360deg); } }
+    </style>
+</head>
+<body>
+    <h1>Checking your browser...</h1>
+    <div class="spinner"></div>
+    <p>This is an automatic security check. Please wait.</p>
+    <noscript>Please enable JavaScript to continue.</noscript>
+    <script>
+    (async function () {
+        var token = "{token}", difficulty = {difficulty}, encoder = new TextEncoder();
+        function solved(hash) {
+            var bits = difficulty;
+            for (var i = 0; bits > 0; i++) {
+                if (bits >= 8) { if (hash[i] !== 0) return false; bits -= 8; }
+                else return (hash[i] >> (8 - bits)) === 0;
+            }
+            return true;
+        }
+        for (var nonce = 0; ; nonce++) {
+            var hash = new Uint8Array(await crypto.subtle.digest("SHA-256", encoder.encode(token + ":" + nonce)));
+            if (solved(hash)) break;
+        }
+        location.replace("{verify}?token=" + token + "&nonce=" + nonce + "&return=" + encodeURIComponent({return}));
+    })();
+    </script>
+</body>
+</html>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request(path: &str, cookie: Option<String>) -> HttpRequest {
+        HttpRequest {
+            client_ip: "198.51.100.7".parse().unwrap(),
+            method: "GET".to_string(),
+            path: path.to_string(),
+            host: "shop.example.com".to_string(),
+            destination: Some("10.0.0.1".parse().unwrap()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            referer: None,
+            cookie,
+            headers: HashMap::new(),
+            body_size: 0,
+        }
+    }
+
+    fn service() -> ChallengeService {
+        ChallengeService::new(ChallengeConfig {
+            difficulty: 4,
+            min_difficulty: 1,
+            ..Default::default()
+        })
+    }
+
+    fn solve(body: &str, difficulty: u8) -> (String, u64) {
+        let start = body.find("var token = \"").unwrap() + 13;
+        let token = &body[start..start + body[start..].find('"').unwrap()];
+        let nonce = (0u64..)
+            .find(|n| leading_zero_bits(&Sha256::digest(format!("{}:{}", token, n).as_bytes())) >= difficulty as u32)
+            .unwrap();
+        (token.to_string(), nonce)
+    }
+
+    #[test]
+    fn test_challenge_pass_and_reentry() {
+        let challenges = service();
+        assert!(matches!(challenges.inspect(&request("/cart", None)), Diversion::Pass));
+
+        challenges.protect_destination("10.0.0.1".parse().unwrap(), None);
+        let Diversion::Respond(page) = challenges.inspect(&request("/cart", None)) else {
+            panic!("expected a challenge page");
+        };
+        assert_eq!(page.status, 503);
+
+        let (token, nonce) = solve(&page.body, challenges.difficulty());
+        let verify = format!("{}?token={}&nonce={}&return=%2Fcart", challenges.config().verify_path, token, nonce);
+        let Diversion::Respond(answer) = challenges.inspect(&request(&verify, None)) else {
+            panic!("expected a redirect");
+        };
+        assert_eq!(answer.status, 302);
+        let location = answer.headers.iter().find(|(k, _)| k == "Location").unwrap();
+        assert_eq!(location.1, "/cart");
+        let cookie = answer.headers.iter().find(|(k, _)| k == "Set-Cookie").unwrap();
+        let cookie = cookie.1.split(';').next().unwrap().to_string();
+
+        assert!(matches!(challenges.inspect(&request("/cart", Some(cookie))), Diversion::Pass));
+
+        // The same answer cannot be spent twice
+        assert!(matches!(
+            challenges.inspect(&request(&verify, None)),
+            Diversion::Respond(ChallengeResponse { status: 403, .. })
+        ));
+        assert_eq!(challenges.client(&"198.51.100.7".parse().unwrap()).unwrap().passed, 1);
+    }
+
+    #[test]
+    fn test_repeated_failures_block() {
+        let challenges = service();
+        let verify = format!("{}?token=1.4.00.ff&nonce=1", challenges.config().verify_path);
+        for _ in 0..challenges.config().max_failures - 1 {
+            assert!(matches!(challenges.inspect(&request(&verify, None)), Diversion::Respond(_)));
+        }
+        assert!(matches!(challenges.inspect(&request(&verify, None)), Diversion::Block { .. }));
+        assert!(matches!(challenges.inspect(&request("/", None)), Diversion::Block { .. }));
+    }
+
+    #[test]
+    fn test_tuning_follows_solve_rate() {
+        let challenges = service();
+        challenges.window_issued.store(100, Ordering::Relaxed);
+        challenges.window_passed.store(98, Ordering::Relaxed);
+        assert_eq!(challenges.tune().difficulty, 5);
+
+        challenges.window_issued.store(100, Ordering::Relaxed);
+        challenges.window_passed.store(5, Ordering::Relaxed);
+        assert_eq!(challenges.tune().difficulty, 4);
+    }
+
+    struct StubVerifier;
+
+    #[async_trait::async_trait]
+    impl CaptchaVerifier for StubVerifier {
+        async fn verify(&self, response: &str, _client_ip: IpAddr) -> Result<bool, String> {
+            Ok(response == "ok")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failures_escalate_to_captcha() {
+        let config = ChallengeConfig {
+            difficulty: 4,
+            min_difficulty: 1,
+            captcha: Some(CaptchaConfig::new(captcha::CaptchaProvider::Turnstile, "site-key")),
+            ..Default::default()
+        };
+        let challenges = ChallengeService::new(config).with_captcha_verifier(Arc::new(StubVerifier));
+        challenges.protect_destination("10.0.0.1".parse().unwrap(), None);
+
+        let verify = format!("{}?token=1.4.00.ff&nonce=1", challenges.config().verify_path);
+        for _ in 0..2 {
+            challenges.inspect(&request(&verify, None));
+        }
+
+        let Diversion::Respond(page) = challenges.inspect(&request("/cart", None)) else {
+            panic!("expected a CAPTCHA page");
+        };
+        assert!(page.body.contains("cf-turnstile"));
+        let start = page.body.find("name=\"token\" value=\"").unwrap() + 20;
+        let token = &page.body[start..start + page.body[start..].find('"').unwrap()];
+
+        let answer = format!("/.well-known/osddos/captcha?token={}&return=%2Fcart&cf-turnstile-response=ok", token);
+        let Diversion::VerifyCaptcha(submission) = challenges.inspect(&request(&answer, None)) else {
+            panic!("expected a CAPTCHA submission");
+        };
+        let Diversion::Respond(redirect) = challenges.verify_captcha(submission).await else {
+            panic!("expected a redirect");
+        };
+        assert_eq!(redirect.status, 302);
+        let client = challenges.client(&"198.51.100.7".parse().unwrap()).unwrap();
+        assert!(!client.captcha_required);
+        assert_eq!(challenges.stats().captchas_passed, 1);
+
+        // The same token cannot be spent twice
+        assert!(matches!(
+            challenges.inspect(&request(&answer, None)),
+            Diversion::Respond(ChallengeResponse { status: 403, .. })
+        ));
+    }
+}
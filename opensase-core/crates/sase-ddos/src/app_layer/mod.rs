@@ -1,10 +1,20 @@
 //! Application Layer Defense
 //!
 //! HTTP challenge, bot detection, and L7 rate limiting.
+//!
+//! - [`challenge`]: proof-of-work challenges and pass cookies for HTTP floods
+//! - [`captcha`]: CAPTCHA escalation for clients that fail proof-of-work
+
+pub mod captcha;
+pub mod challenge;
+
+pub use captcha::{CaptchaConfig, CaptchaProvider, CaptchaVerifier, SiteVerifier};
+pub use challenge::{ChallengeConfig, ChallengeService, Diversion};
 
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use dashmap::DashMap;
 
@@ -22,6 +32,8 @@ pub struct AppLayerDefense {
     config: AppLayerConfig,
     /// Statistics
     stats: AppLayerStats,
+    /// Proof-of-work challenges served by the L7 gateway
+    challenges: Option<Arc<ChallengeService>>,
 }
 
 #[derive(Debug, Clone)]
@@ -133,6 +145,8 @@ pub struct HttpRequest {
     pub method: String,
     pub path: String,
     pub host: String,
+    /// Origin address the request is bound for, when the gateway knows it
+    pub destination: Option<IpAddr>,
     pub user_agent: Option<String>,
     pub referer: Option<String>,
     pub cookie: Option<String>,
@@ -158,9 +172,17 @@ impl AppLayerDefense {
             bot_signatures: default_bot_signatures(),
             config,
             stats: AppLayerStats::default(),
+            challenges: None,
         }
     }
     
+    /// Send challenged clients to a challenge service, and let clients
+    /// holding its pass cookie in as verified
+    pub fn with_challenge_service(mut self, challenges: Arc<ChallengeService>) -> Self {
+        self.challenges = Some(challenges);
+        self
+    }
+    
     /// Analyze request and decide action
     pub fn analyze(&self, request: &HttpRequest) -> RequestDecision {
         self.stats.requests_total.fetch_add(1, Ordering::Relaxed);
@@ -172,6 +194,9 @@ impl AppLayerDefense {
                 return self.check_rate_limit(request);
             }
         }
+        if self.challenges.as_ref().is_some_and(|c| c.has_pass(request)) {
+            return self.check_rate_limit(request);
+        }
         
         // Check for bot signatures
         if let Some(action) = self.detect_bot(request) {
@@ -251,6 +276,13 @@ impl AppLayerDefense {
     }
     
     fn issue_challenge(&self, request: &HttpRequest) -> RequestDecision {
+        // The service serves the page at the client's next request
+        if let Some(challenges) = &self.challenges {
+            challenges.divert_client(request.client_ip);
+            self.stats.challenges_issued.fetch_add(1, Ordering::Relaxed);
+            return RequestDecision::Challenge(ChallengeType::ProofOfWork);
+        }
+        
         let challenge_type = if self.config.js_challenge_enabled {
             ChallengeType::JavaScript
         } else {
@@ -0,0 +1,722 @@
+//! Traffic Baseline Learning
+//!
+//! Adaptive baseline for zero false positive detection.
+//!
+//! Every sample updates the destination's exponentially weighted mean and
+//! variance of pps and bps, an exponentially weighted protocol mix, and the
+//! same statistics for the current hour of the week. Traffic is then scored
+//! in standard deviations above what is normal *for that hour*: a backup
+//! window that doubles traffic every Sunday night stops looking like an
+//! attack once a few weeks of Sundays have been seen.
+//!
+//! Each hour-of-week slot also tracks streaming p50/p95/p99 estimates.
+//! Bursty services have heavy tails the variance understates, so traffic
+//! inside the hour's usual p99 is never scored as anomalous.
+//!
+//! Destinations inside a protected prefix also teach the prefix's
+//! baseline, which stands in for hosts too new or too quiet to have
+//! matured their own.
+
+pub mod store;
+
+pub use store::{BaselineStore, FileBaselineStore};
+
+use crate::{Protocol, TrafficBaseline, TrafficSample};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use dashmap::DashMap;
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Slots in the seasonal profile, one per hour of the week
+pub const HOURS_PER_WEEK: usize = 168;
+
+/// Distinct destination ports tracked per destination
+const MAX_PORTS: usize = 1024;
+
+/// Baseline learner with exponential moving average
+pub struct BaselineLearner {
+    /// Learning rate (0-1)
+    alpha: f64,
+    /// Minimum samples before baseline is valid
+    min_samples: u64,
+    /// Samples an hour-of-week slot needs before it replaces the overall
+    /// statistics
+    min_seasonal_samples: u64,
+    /// Floor on the standard deviation, as a fraction of the mean, so a
+    /// very steady destination doesn't alarm on small wobbles
+    min_relative_std: f64,
+    /// Score added for a complete change of protocol mix
+    mix_weight: f64,
+    /// Per-destination baselines
+    baselines: DashMap<IpAddr, LearnedBaseline>,
+    /// Protected prefixes, learned as a whole
+    prefixes: parking_lot::RwLock<Vec<IpNetwork>>,
+    /// Per-prefix baselines
+    prefix_baselines: DashMap<IpNetwork, LearnedBaseline>,
+}
+
+/// Exponentially weighted mean and variance
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Ewma {
+    pub mean: f64,
+    pub variance: f64,
+    pub samples: u64,
+}
+
+impl Ewma {
+    fn update(&mut self, value: f64, alpha: f64) {
+        self.samples += 1;
+        if self.samples == 1 {
+            self.mean = value;
+            self.variance = 0.0;
+            return;
+        }
+        let diff = value - self.mean;
+        let increment = alpha * diff;
+        self.mean += increment;
+        self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance.max(0.0).sqrt()
+    }
+}
+
+/// Streaming p50/p95/p99 estimates
+///
+/// Each estimate moves towards the sample by a step proportional to the
+/// slot's spread, up with weight `q` and down with weight `1 - q`, so it
+/// settles where a fraction `q` of samples fall below it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Quantiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub samples: u64,
+}
+
+impl Quantiles {
+    fn update(&mut self, value: f64, alpha: f64, spread: f64) {
+        self.samples += 1;
+        if self.samples == 1 {
+            self.p50 = value;
+            self.p95 = value;
+            self.p99 = value;
+            return;
+        }
+        let step = alpha * spread;
+        for (estimate, q) in [(&mut self.p50, 0.5), (&mut self.p95, 0.95), (&mut self.p99, 0.99)] {
+            *estimate += if value > *estimate { step * q } else { -step * (1.0 - q) };
+        }
+    }
+}
+
+/// Learned statistics for one destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnedBaseline {
+    pub samples: u64,
+    pub pps: Ewma,
+    pub bps: Ewma,
+    /// Rate of connection attempts (SYN without ACK)
+    pub cps: Ewma,
+    /// Weighted share of samples per protocol; sums to 1
+    pub protocol_mix: Vec<(Protocol, f64)>,
+    pub port_counts: HashMap<u16, u64>,
+    /// pps per hour of week, Monday 00:00 UTC first
+    pub seasonal_pps: Vec<Ewma>,
+    /// bps per hour of week, Monday 00:00 UTC first
+    pub seasonal_bps: Vec<Ewma>,
+    /// pps percentiles per hour of week
+    #[serde(default)]
+    pub seasonal_pps_quantiles: Vec<Quantiles>,
+    /// bps percentiles per hour of week
+    #[serde(default)]
+    pub seasonal_bps_quantiles: Vec<Quantiles>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How far observed traffic sits above its baseline
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnomalyScore {
+    /// Standard deviations above expected pps; zero when below
+    pub pps_z: f64,
+    /// Standard deviations above expected bps; zero when below
+    pub bps_z: f64,
+    /// Total variation distance between observed and learned protocol mix
+    pub mix_shift: f64,
+    pub expected_pps: f64,
+    pub expected_bps: f64,
+    /// Expectation came from the hour-of-week profile
+    pub seasonal: bool,
+    /// The hour's p99 pps, once its percentiles have matured
+    pub p99_pps: Option<f64>,
+    /// Scored against this protected prefix's baseline rather than the
+    /// destination's own
+    pub prefix: Option<IpNetwork>,
+    /// Combined score the detector compares against its threshold
+    pub score: f64,
+}
+
+impl BaselineLearner {
+    pub fn new(alpha: f64, min_samples: u64) -> Self {
+        Self {
+            alpha,
+            min_samples,
+            min_seasonal_samples: min_samples,
+            min_relative_std: 0.1,
+            mix_weight: 3.0,
+            baselines: DashMap::new(),
+            prefixes: parking_lot::RwLock::new(Vec::new()),
+            prefix_baselines: DashMap::new(),
+        }
+    }
+
+    /// Learn a prefix as a whole, alongside its destinations
+    pub fn protect_prefix(&self, prefix: IpNetwork) {
+        let mut prefixes = self.prefixes.write();
+        if !prefixes.contains(&prefix) {
+            prefixes.push(prefix);
+            // Most specific first, so a destination learns its closest prefix
+            prefixes.sort_by(|a, b| b.prefix().cmp(&a.prefix()));
+        }
+    }
+
+    /// Stop learning a prefix and forget its baseline
+    pub fn unprotect_prefix(&self, prefix: &IpNetwork) -> bool {
+        let mut prefixes = self.prefixes.write();
+        let before = prefixes.len();
+        prefixes.retain(|p| p != prefix);
+        self.prefix_baselines.remove(prefix);
+        prefixes.len() != before
+    }
+
+    pub fn protected_prefixes(&self) -> Vec<IpNetwork> {
+        self.prefixes.read().clone()
+    }
+
+    /// Closest protected prefix covering a destination
+    pub fn prefix_for(&self, destination: &IpAddr) -> Option<IpNetwork> {
+        self.prefixes.read().iter().find(|p| p.contains(*destination)).copied()
+    }
+
+    pub fn with_min_seasonal_samples(mut self, samples: u64) -> Self {
+        self.min_seasonal_samples = samples;
+        self
+    }
+
+    pub fn with_min_relative_std(mut self, fraction: f64) -> Self {
+        self.min_relative_std = fraction;
+        self
+    }
+
+    pub fn with_mix_weight(mut self, weight: f64) -> Self {
+        self.mix_weight = weight;
+        self
+    }
+
+    /// Update baseline with new sample
+    pub fn learn(&self, sample: &TrafficSample) {
+        self.learn_at(sample, Utc::now());
+    }
+
+    /// Update baseline with a sample observed at `at`
+    pub fn learn_at(&self, sample: &TrafficSample, at: DateTime<Utc>) {
+        self.baselines
+            .entry(sample.destination)
+            .or_insert_with(|| LearnedBaseline::new(at))
+            .learn(sample, at, self.alpha);
+
+        if let Some(prefix) = self.prefix_for(&sample.destination) {
+            self.prefix_baselines
+                .entry(prefix)
+                .or_insert_with(|| LearnedBaseline::new(at))
+                .learn(sample, at, self.alpha);
+        }
+    }
+
+    /// Whether a destination has seen enough traffic to be scored
+    pub fn is_mature(&self, destination: &IpAddr) -> bool {
+        self.baselines.get(destination)
+            .map(|b| b.samples >= self.min_samples)
+            .unwrap_or(false)
+    }
+
+    /// Score observed rates and protocol mix against the destination's
+    /// baseline for the hour of week of `at`, or its protected prefix's
+    /// while its own is immature. `None` until either is mature.
+    pub fn score(
+        &self,
+        destination: &IpAddr,
+        pps: f64,
+        bps: f64,
+        protocol_mix: &HashMap<Protocol, f64>,
+        at: DateTime<Utc>,
+    ) -> Option<AnomalyScore> {
+        if let Some(baseline) = self.baselines.get(destination) {
+            if baseline.samples >= self.min_samples {
+                return Some(self.score_against(&baseline, None, pps, bps, protocol_mix, at));
+            }
+        }
+
+        let prefix = self.prefix_for(destination)?;
+        let baseline = self.prefix_baselines.get(&prefix)?;
+        if baseline.samples < self.min_samples {
+            return None;
+        }
+        Some(self.score_against(&baseline, Some(prefix), pps, bps, protocol_mix, at))
+    }
+
+    fn score_against(
+        &self,
+        baseline: &LearnedBaseline,
+        prefix: Option<IpNetwork>,
+        pps: f64,
+        bps: f64,
+        protocol_mix: &HashMap<Protocol, f64>,
+        at: DateTime<Utc>,
+    ) -> AnomalyScore {
+        let slot = hour_of_week(at);
+        let seasonal_pps = &baseline.seasonal_pps[slot];
+        let seasonal_bps = &baseline.seasonal_bps[slot];
+        let seasonal = seasonal_pps.samples >= self.min_seasonal_samples;
+        let (expected_pps, expected_bps) = if seasonal {
+            (*seasonal_pps, *seasonal_bps)
+        } else {
+            (baseline.pps, baseline.bps)
+        };
+
+        let mut pps_z = self.z_score(pps, &expected_pps);
+        let mut bps_z = self.z_score(bps, &expected_bps);
+
+        // Within the hour's usual tail is normal, whatever the variance says
+        let pps_quantiles = baseline.seasonal_pps_quantiles.get(slot).copied().unwrap_or_default();
+        let bps_quantiles = baseline.seasonal_bps_quantiles.get(slot).copied().unwrap_or_default();
+        let tail = seasonal && pps_quantiles.samples >= self.min_seasonal_samples;
+        if tail {
+            pps_z = pps_z.min(self.tail_score(pps, pps_quantiles.p99, &expected_pps));
+            bps_z = bps_z.min(self.tail_score(bps, bps_quantiles.p99, &expected_bps));
+        }
+
+        let mix_shift = if protocol_mix.is_empty() {
+            0.0
+        } else {
+            baseline.mix_distance(protocol_mix)
+        };
+
+        AnomalyScore {
+            pps_z,
+            bps_z,
+            mix_shift,
+            expected_pps: expected_pps.mean,
+            expected_bps: expected_bps.mean,
+            seasonal,
+            p99_pps: tail.then_some(pps_quantiles.p99),
+            prefix,
+            score: pps_z.max(bps_z) + self.mix_weight * mix_shift,
+        }
+    }
+
+    fn sigma(&self, expected: &Ewma) -> f64 {
+        expected.std_dev()
+            .max(expected.mean * self.min_relative_std)
+            .max(1.0)
+    }
+
+    fn z_score(&self, value: f64, expected: &Ewma) -> f64 {
+        ((value - expected.mean) / self.sigma(expected)).max(0.0)
+    }
+
+    /// Standard deviations above the hour's p99
+    fn tail_score(&self, value: f64, p99: f64, expected: &Ewma) -> f64 {
+        ((value - p99) / self.sigma(expected)).max(0.0)
+    }
+
+    /// The hour-of-week percentiles of pps and bps for a destination
+    pub fn hourly_percentiles(&self, destination: &IpAddr, at: DateTime<Utc>) -> Option<(Quantiles, Quantiles)> {
+        let baseline = self.baselines.get(destination)?;
+        let slot = hour_of_week(at);
+        Some((
+            baseline.seasonal_pps_quantiles.get(slot).copied().unwrap_or_default(),
+            baseline.seasonal_bps_quantiles.get(slot).copied().unwrap_or_default(),
+        ))
+    }
+
+    /// Raw learned statistics for a protected prefix
+    pub fn learned_prefix(&self, prefix: &IpNetwork) -> Option<LearnedBaseline> {
+        self.prefix_baselines.get(prefix).map(|b| b.clone())
+    }
+
+    /// Get baseline for destination
+    pub fn get_baseline(&self, destination: &IpAddr) -> Option<TrafficBaseline> {
+        let b = self.baselines.get(destination)?;
+        if b.samples < self.min_samples {
+            return None;
+        }
+
+        let total_ports: u64 = b.port_counts.values().sum();
+        let port_distribution = b.port_counts.iter()
+            .map(|(port, count)| (*port, *count as f64 / total_ports.max(1) as f64))
+            .collect();
+
+        Some(TrafficBaseline {
+            target: *destination,
+            normal_pps: b.pps.mean as u64,
+            normal_bps: b.bps.mean as u64,
+            normal_connections_per_sec: b.cps.mean as u64,
+            protocol_distribution: b.protocol_mix.iter().copied().collect(),
+            port_distribution,
+            geo_distribution: HashMap::new(),
+            updated_at: b.updated_at,
+        })
+    }
+
+    /// Raw learned statistics for a destination
+    pub fn learned(&self, destination: &IpAddr) -> Option<LearnedBaseline> {
+        self.baselines.get(destination).map(|b| b.clone())
+    }
+
+    /// Check if current traffic is anomalous
+    pub fn is_anomaly(&self, destination: &IpAddr, pps: u64, bps: u64, threshold: f64) -> bool {
+        if let Some(baseline) = self.get_baseline(destination) {
+            baseline.is_anomaly(pps, bps, threshold)
+        } else {
+            // No baseline - use absolute thresholds
+            pps > 100_000 || bps > 1_000_000_000
+        }
+    }
+
+    /// Get expected traffic for current hour
+    pub fn get_hourly_expected(&self, destination: &IpAddr) -> Option<u64> {
+        self.baselines.get(destination).map(|b| {
+            let slot = &b.seasonal_pps[hour_of_week(Utc::now())];
+            if slot.samples >= self.min_seasonal_samples {
+                slot.mean as u64
+            } else {
+                b.pps.mean as u64
+            }
+        })
+    }
+
+    /// Decay old data (call periodically)
+    pub fn decay(&self, factor: f64) {
+        for mut entry in self.baselines.iter_mut() {
+            let baseline = entry.value_mut();
+            baseline.pps.mean *= factor;
+            baseline.bps.mean *= factor;
+        }
+    }
+
+    /// Forget a destination, e.g. after it moved to a new service
+    pub fn reset(&self, destination: &IpAddr) {
+        self.baselines.remove(destination);
+    }
+
+    /// Copy of every baseline, for persistence
+    pub fn snapshot(&self) -> BaselineSnapshot {
+        BaselineSnapshot {
+            destinations: self.baselines.iter()
+                .map(|entry| (*entry.key(), entry.value().clone()))
+                .collect(),
+            prefixes: self.prefix_baselines.iter()
+                .map(|entry| (*entry.key(), entry.value().clone()))
+                .collect(),
+        }
+    }
+
+    /// Load saved baselines; returns how many were restored. Saved
+    /// prefixes are protected again.
+    pub fn restore(&self, store: &dyn BaselineStore) -> Result<usize, String> {
+        let snapshot = store.load()?;
+        let count = snapshot.len();
+        for (destination, mut baseline) in snapshot.destinations {
+            baseline.normalize();
+            self.baselines.insert(destination, baseline);
+        }
+        for (prefix, mut baseline) in snapshot.prefixes {
+            baseline.normalize();
+            self.protect_prefix(prefix);
+            self.prefix_baselines.insert(prefix, baseline);
+        }
+        tracing::info!("Restored {} traffic baselines", count);
+        Ok(count)
+    }
+
+    /// Save every baseline; returns how many were written
+    pub fn persist(&self, store: &dyn BaselineStore) -> Result<usize, String> {
+        let snapshot = self.snapshot();
+        store.save(&snapshot)?;
+        Ok(snapshot.len())
+    }
+
+    /// Persist on an interval
+    pub fn spawn_persistence(
+        self: Arc<Self>,
+        store: Arc<dyn BaselineStore>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                let learner = self.clone();
+                let store = store.clone();
+                let result = tokio::task::spawn_blocking(move || learner.persist(store.as_ref())).await;
+                match result {
+                    Ok(Ok(count)) => tracing::debug!("Persisted {} traffic baselines", count),
+                    Ok(Err(e)) => tracing::warn!("Baseline persistence failed: {}", e),
+                    Err(e) => tracing::warn!("Baseline persistence task failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+impl LearnedBaseline {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            samples: 0,
+            pps: Ewma::default(),
+            bps: Ewma::default(),
+            cps: Ewma::default(),
+            protocol_mix: Vec::new(),
+            port_counts: HashMap::new(),
+            seasonal_pps: vec![Ewma::default(); HOURS_PER_WEEK],
+            seasonal_bps: vec![Ewma::default(); HOURS_PER_WEEK],
+            seasonal_pps_quantiles: vec![Quantiles::default(); HOURS_PER_WEEK],
+            seasonal_bps_quantiles: vec![Quantiles::default(); HOURS_PER_WEEK],
+            updated_at: now,
+        }
+    }
+
+    /// Profiles saved by another build may have a different shape
+    fn normalize(&mut self) {
+        self.seasonal_pps.resize(HOURS_PER_WEEK, Ewma::default());
+        self.seasonal_bps.resize(HOURS_PER_WEEK, Ewma::default());
+        self.seasonal_pps_quantiles.resize(HOURS_PER_WEEK, Quantiles::default());
+        self.seasonal_bps_quantiles.resize(HOURS_PER_WEEK, Quantiles::default());
+    }
+
+    fn learn(&mut self, sample: &TrafficSample, at: DateTime<Utc>, alpha: f64) {
+        self.samples += 1;
+        self.updated_at = at;
+
+        let pps = sample.pps as f64;
+        let bps = sample.bps as f64;
+        self.pps.update(pps, alpha);
+        self.bps.update(bps, alpha);
+
+        // SYN without ACK opens a connection
+        let opening = sample.protocol == Protocol::Tcp
+            && sample.tcp_flags.is_some_and(|f| f & 0x02 != 0 && f & 0x10 == 0);
+        self.cps.update(if opening { pps } else { 0.0 }, alpha);
+
+        let slot = hour_of_week(at);
+        self.seasonal_pps[slot].update(pps, alpha);
+        self.seasonal_bps[slot].update(bps, alpha);
+        let pps_spread = spread(&self.seasonal_pps[slot]);
+        let bps_spread = spread(&self.seasonal_bps[slot]);
+        self.seasonal_pps_quantiles[slot].update(pps, alpha, pps_spread);
+        self.seasonal_bps_quantiles[slot].update(bps, alpha, bps_spread);
+
+        // Decay every share, then credit this sample's protocol
+        let rate = if self.samples == 1 { 1.0 } else { alpha };
+        for (_, share) in self.protocol_mix.iter_mut() {
+            *share *= 1.0 - rate;
+        }
+        match self.protocol_mix.iter_mut().find(|(p, _)| *p == sample.protocol) {
+            Some((_, share)) => *share += rate,
+            None => self.protocol_mix.push((sample.protocol, rate)),
+        }
+        self.protocol_mix.retain(|(_, share)| *share > 1e-4);
+
+        if self.port_counts.len() < MAX_PORTS || self.port_counts.contains_key(&sample.dst_port) {
+            *self.port_counts.entry(sample.dst_port).or_default() += 1;
+        }
+    }
+
+    /// Total variation distance to an observed mix, in [0, 1]
+    fn mix_distance(&self, observed: &HashMap<Protocol, f64>) -> f64 {
+        let total: f64 = observed.values().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        let mut distance: f64 = self.protocol_mix.iter()
+            .map(|(p, learned)| (observed.get(p).copied().unwrap_or(0.0) / total - learned).abs())
+            .sum();
+        distance += observed.iter()
+            .filter(|(p, _)| !self.protocol_mix.iter().any(|(q, _)| q == *p))
+            .map(|(_, share)| share / total)
+            .sum::<f64>();
+        (distance / 2.0).min(1.0)
+    }
+}
+
+/// Step scale for percentile tracking: the slot's spread, floored so a
+/// steady slot's estimates still move
+fn spread(slot: &Ewma) -> f64 {
+    slot.std_dev().max(slot.mean.abs() * 0.05).max(1.0)
+}
+
+/// Every learned baseline, as persisted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BaselineSnapshot {
+    pub destinations: Vec<(IpAddr, LearnedBaseline)>,
+    #[serde(default)]
+    pub prefixes: Vec<(IpNetwork, LearnedBaseline)>,
+}
+
+impl BaselineSnapshot {
+    pub fn len(&self) -> usize {
+        self.destinations.len() + self.prefixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Hour of the week, Monday 00:00 UTC = 0
+pub fn hour_of_week(at: DateTime<Utc>) -> usize {
+    at.weekday().num_days_from_monday() as usize * 24 + at.hour() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn sample(protocol: Protocol, pps: u64) -> TrafficSample {
+        TrafficSample {
+            timestamp: Instant::now(),
+            source: "1.2.3.4".parse().unwrap(),
+            destination: "10.0.0.1".parse().unwrap(),
+            protocol,
+            src_port: 12345,
+            dst_port: 443,
+            packet_size: 1500,
+            tcp_flags: Some(0x10),
+            pps,
+            bps: pps * 8000,
+        }
+    }
+
+    #[test]
+    fn test_baseline_learning() {
+        let learner = BaselineLearner::new(0.1, 10);
+
+        // Learn normal traffic
+        for i in 0..20 {
+            let sample = TrafficSample {
+                timestamp: Instant::now(),
+                source: "1.2.3.4".parse().unwrap(),
+                destination: "10.0.0.1".parse().unwrap(),
+                protocol: Protocol::Tcp,
+                src_port: 12345,
+                dst_port: 443,
+                packet_size: 1500,
+                tcp_flags: Some(0x10),
+                pps: 10000 + (i * 100),
+                bps: 100_000_000,
+            };
+            learner.learn(&sample);
+        }
+
+        let baseline = learner.get_baseline(&"10.0.0.1".parse().unwrap());
+        assert!(baseline.is_some());
+
+        let bl = baseline.unwrap();
+        assert!(bl.normal_pps > 0);
+        assert!(bl.normal_bps > 0);
+    }
+
+    #[test]
+    fn test_seasonal_score() {
+        let learner = BaselineLearner::new(0.1, 10);
+        let destination: IpAddr = "10.0.0.1".parse().unwrap();
+        // Monday 03:00 is quiet, Sunday 23:00 runs backups
+        let quiet = "2024-01-01T03:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let busy = "2024-01-07T23:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        for _ in 0..50 {
+            learner.learn_at(&sample(Protocol::Tcp, 10_000), quiet);
+            learner.learn_at(&sample(Protocol::Tcp, 50_000), busy);
+        }
+
+        let tcp: HashMap<Protocol, f64> = [(Protocol::Tcp, 1.0)].into_iter().collect();
+        let at_busy = learner.score(&destination, 50_000.0, 400_000_000.0, &tcp, busy).unwrap();
+        assert!(at_busy.seasonal);
+        assert!(at_busy.score < 1.0);
+
+        let at_quiet = learner.score(&destination, 50_000.0, 400_000_000.0, &tcp, quiet).unwrap();
+        assert!(at_quiet.score > 10.0);
+
+        // Same volume, but all UDP
+        let udp: HashMap<Protocol, f64> = [(Protocol::Udp, 1.0)].into_iter().collect();
+        let shifted = learner.score(&destination, 50_000.0, 400_000_000.0, &udp, busy).unwrap();
+        assert!(shifted.mix_shift > 0.99);
+        assert!(shifted.score > at_busy.score + 2.0);
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let learner = BaselineLearner::new(0.1, 10);
+        for _ in 0..20 {
+            learner.learn(&sample(Protocol::Udp, 5_000));
+        }
+
+        let json = serde_json::to_vec(&learner.snapshot()).unwrap();
+        let restored: BaselineSnapshot = serde_json::from_slice(&json).unwrap();
+        assert_eq!(restored.destinations.len(), 1);
+        assert_eq!(restored.destinations[0].1.samples, 20);
+        assert_eq!(restored.destinations[0].1.seasonal_pps.len(), HOURS_PER_WEEK);
+        assert_eq!(restored.destinations[0].1.seasonal_pps_quantiles.len(), HOURS_PER_WEEK);
+    }
+
+    #[test]
+    fn test_bursty_hour_within_p99() {
+        let learner = BaselineLearner::new(0.05, 10);
+        let destination: IpAddr = "10.0.0.1".parse().unwrap();
+        let at = "2024-01-03T14:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        // Mostly 10K pps with a burst to 40K every twentieth sample
+        for i in 0..4000 {
+            let pps = if i % 20 == 0 { 40_000 } else { 10_000 };
+            learner.learn_at(&sample(Protocol::Tcp, pps), at);
+        }
+
+        let (pps, _) = learner.hourly_percentiles(&destination, at).unwrap();
+        assert!(pps.p50 < 15_000.0);
+        assert!(pps.p99 > 25_000.0);
+
+        let tcp: HashMap<Protocol, f64> = [(Protocol::Tcp, 1.0)].into_iter().collect();
+        let burst = learner.score(&destination, 30_000.0, 240_000_000.0, &tcp, at).unwrap();
+        assert!(burst.p99_pps.is_some());
+        assert!(burst.pps_z < 1.0);
+
+        let flood = learner.score(&destination, 400_000.0, 3_200_000_000.0, &tcp, at).unwrap();
+        assert!(flood.score > 10.0);
+    }
+
+    #[test]
+    fn test_prefix_baseline_covers_new_hosts() {
+        let learner = BaselineLearner::new(0.1, 10);
+        learner.protect_prefix("10.0.0.0/24".parse().unwrap());
+        for _ in 0..20 {
+            learner.learn(&sample(Protocol::Tcp, 10_000));
+        }
+
+        let tcp: HashMap<Protocol, f64> = [(Protocol::Tcp, 1.0)].into_iter().collect();
+        let fresh: IpAddr = "10.0.0.77".parse().unwrap();
+        let score = learner.score(&fresh, 10_000.0, 80_000_000.0, &tcp, Utc::now()).unwrap();
+        assert_eq!(score.prefix, Some("10.0.0.0/24".parse().unwrap()));
+
+        let outside: IpAddr = "10.0.1.1".parse().unwrap();
+        assert!(learner.score(&outside, 10_000.0, 80_000_000.0, &tcp, Utc::now()).is_none());
+    }
+}
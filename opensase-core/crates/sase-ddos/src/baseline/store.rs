@@ -0,0 +1,65 @@
+//! Baseline Persistence
+//!
+//! Learned baselines take days to converge and a week to fill the
+//! hour-of-week profile, so they are saved periodically and restored at
+//! startup rather than relearned after every restart.
+
+use super::{BaselineSnapshot, LearnedBaseline};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Durable copy of the learner's baselines
+pub trait BaselineStore: Send + Sync {
+    /// Every saved baseline, read once at startup
+    fn load(&self) -> Result<BaselineSnapshot, String>;
+    /// Replace the saved set
+    fn save(&self, snapshot: &BaselineSnapshot) -> Result<(), String>;
+}
+
+/// Files written before prefix baselines held a bare destination list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedBaselines {
+    Snapshot(BaselineSnapshot),
+    Destinations(Vec<(IpAddr, LearnedBaseline)>),
+}
+
+/// All baselines in one JSON file
+pub struct FileBaselineStore {
+    path: PathBuf,
+}
+
+impl FileBaselineStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl BaselineStore for FileBaselineStore {
+    fn load(&self) -> Result<BaselineSnapshot, String> {
+        let content = match std::fs::read(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BaselineSnapshot::default()),
+            Err(e) => return Err(format!("{}: {}", self.path.display(), e)),
+        };
+        let saved = serde_json::from_slice(&content).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        Ok(match saved {
+            SavedBaselines::Snapshot(snapshot) => snapshot,
+            SavedBaselines::Destinations(destinations) => BaselineSnapshot { destinations, prefixes: Vec::new() },
+        })
+    }
+
+    fn save(&self, snapshot: &BaselineSnapshot) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        let content = serde_json::to_vec(snapshot).map_err(|e| e.to_string())?;
+
+        // Write aside and rename so a crash never leaves a truncated file
+        let partial = self.path.with_extension("partial");
+        std::fs::write(&partial, content)
+            .and_then(|_| std::fs::rename(&partial, &self.path))
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
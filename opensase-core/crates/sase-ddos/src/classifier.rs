@@ -8,6 +8,11 @@ use crate::{Attack, AttackMetrics, AttackType, Protocol};
 pub fn classify(attack: &Attack) -> Attack {
     let mut classified = attack.clone();
     
+    // Blended metrics cannot refine a mix of vectors
+    if attack.attack_type == AttackType::MultiVector {
+        return classified;
+    }
+    
     // Use heuristics to refine attack type
     classified.attack_type = classify_by_metrics(&attack.metrics, &attack.target.protocol);
     
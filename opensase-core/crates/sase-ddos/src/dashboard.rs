@@ -1,10 +1,14 @@
 //! Real-Time DDoS Dashboard
 //!
-//! WebSocket-based attack monitoring and alerting.
+//! WebSocket-based attack monitoring and alerting, plus post-mortem
+//! reports rendered from the [`crate::reporting`] timelines.
 
+use crate::reporting::pdf::{Color, Font, PdfDocument, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::reporting::{AttackRecorder, AttackTimeline, TrafficPoint};
 use crate::{Attack, AttackType, AttackStatus, MitigationStats};
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tokio::sync::broadcast;
 
 /// Real-time DDoS dashboard
@@ -15,6 +19,8 @@ pub struct Dashboard {
     active_attacks: dashmap::DashMap<String, AttackTracking>,
     /// Global statistics
     stats: DashboardStats,
+    /// Timelines behind post-mortem reports
+    recorder: Arc<AttackRecorder>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -111,6 +117,10 @@ pub struct AttackReport {
     pub top_sources: Vec<SourceEntry>,
     pub mitigation_timeline: Vec<MitigationEvent>,
     pub effectiveness: f64,
+    /// Traffic at one-second granularity
+    pub traffic_curve: Vec<TrafficPoint>,
+    /// Set when the start of the curve was dropped
+    pub curve_truncated: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -134,9 +144,21 @@ impl Dashboard {
             event_tx: tx,
             active_attacks: dashmap::DashMap::new(),
             stats: DashboardStats::default(),
+            recorder: Arc::new(AttackRecorder::default()),
         }
     }
     
+    /// Share a recorder with [`crate::DdosShield`], so timelines include
+    /// classification and mitigation changes
+    pub fn with_recorder(mut self, recorder: Arc<AttackRecorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+    
+    pub fn recorder(&self) -> Arc<AttackRecorder> {
+        self.recorder.clone()
+    }
+    
     /// Subscribe to attack events
     pub fn subscribe(&self) -> broadcast::Receiver<AttackEvent> {
         self.event_tx.subscribe()
@@ -155,6 +177,7 @@ impl Dashboard {
             packets_passed: AtomicU64::new(0),
         };
         
+        self.recorder.detected(&attack);
         self.active_attacks.insert(attack.id.clone(), tracking);
         self.stats.attacks_detected.fetch_add(1, Ordering::Relaxed);
         
@@ -182,6 +205,7 @@ impl Dashboard {
     
     /// Update attack metrics
     pub fn attack_updated(&self, attack_id: &str, pps: u64, bps: u64, dropped: u64, passed: u64) {
+        self.recorder.traffic(attack_id, pps, bps, dropped, passed);
         if let Some(tracking) = self.active_attacks.get(attack_id) {
             // Update peak
             tracking.peak_pps.fetch_max(pps, Ordering::Relaxed);
@@ -222,6 +246,7 @@ impl Dashboard {
     
     /// Record attack ended
     pub fn attack_ended(&self, attack_id: &str) {
+        self.recorder.ended(attack_id);
         if let Some((_, tracking)) = self.active_attacks.remove(attack_id) {
             self.stats.attacks_mitigated.fetch_add(1, Ordering::Relaxed);
             
@@ -291,8 +316,12 @@ impl Dashboard {
         }
     }
     
-    /// Generate attack report
+    /// Generate attack report, for ongoing attacks and those still
+    /// retained by the recorder
     pub fn generate_report(&self, attack_id: &str) -> Option<AttackReport> {
+        if let Some(timeline) = self.recorder.timeline(attack_id) {
+            return Some(AttackReport::from_timeline(&timeline));
+        }
         self.active_attacks.get(attack_id).map(|tracking| {
             let t = tracking.value();
             let dropped = t.packets_dropped.load(Ordering::Relaxed);
@@ -330,9 +359,29 @@ impl Dashboard {
                 } else {
                     0.0
                 },
+                traffic_curve: Vec::new(),
+                curve_truncated: false,
             }
         })
     }
+    
+    /// Post-mortems of retained attacks, most recent first
+    pub fn recent_reports(&self, limit: usize) -> Vec<AttackReport> {
+        self.recorder.finished(limit).iter()
+            .map(|timeline| AttackReport::from_timeline(timeline))
+            .collect()
+    }
+    
+    /// Post-mortem as pretty-printed JSON
+    pub fn post_mortem_json(&self, attack_id: &str) -> Option<String> {
+        let report = self.generate_report(attack_id)?;
+        serde_json::to_string_pretty(&report).ok()
+    }
+    
+    /// Post-mortem as a PDF document
+    pub fn post_mortem_pdf(&self, attack_id: &str) -> Option<Vec<u8>> {
+        self.generate_report(attack_id).map(|report| report.to_pdf())
+    }
 }
 
 impl Default for Dashboard {
@@ -340,3 +389,166 @@ impl Default for Dashboard {
         Self::new()
     }
 }
+
+// =============================================================================
+// Post-Mortems
+// =============================================================================
+
+const PDF_MARGIN: f32 = 50.0;
+const CHART_HEIGHT: f32 = 180.0;
+/// Curve points plotted across the chart; longer curves are bucketed
+const CHART_POINTS: usize = 480;
+const ATTACK_COLOR: Color = Color(0.8, 0.1, 0.1);
+const DROPPED_COLOR: Color = Color(0.1, 0.5, 0.1);
+
+impl AttackReport {
+    pub fn from_timeline(timeline: &AttackTimeline) -> Self {
+        Self {
+            attack_id: timeline.attack_id.clone(),
+            attack_type: format!("{:?}", timeline.attack_type),
+            target: timeline.target.clone(),
+            start_time: timeline.started_at,
+            end_time: timeline.ended_at,
+            duration_seconds: Some(timeline.duration_seconds()),
+            peak_pps: timeline.peak_pps,
+            peak_bps: timeline.peak_bps,
+            total_packets: timeline.total_packets(),
+            total_bytes: timeline.total_bytes(),
+            unique_sources: timeline.unique_sources,
+            top_sources: timeline.top_sources.iter()
+                .map(|s| SourceEntry {
+                    ip: s.ip.to_string(),
+                    pps: s.pps,
+                    percent: s.pps as f64 / timeline.peak_pps.max(1) as f64 * 100.0,
+                })
+                .collect(),
+            mitigation_timeline: timeline.events.iter()
+                .map(|e| MitigationEvent {
+                    timestamp: e.timestamp,
+                    action: e.kind.as_str().to_string(),
+                    details: e.details.clone(),
+                })
+                .collect(),
+            effectiveness: timeline.effectiveness(),
+            traffic_curve: timeline.curve.iter().cloned().collect(),
+            curve_truncated: timeline.curve_truncated,
+        }
+    }
+    
+    /// Render the report as a printable post-mortem
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut doc = PdfDocument::new();
+        let mut y = PAGE_HEIGHT - PDF_MARGIN;
+        doc.text(PDF_MARGIN, y, Font::Bold, 18.0, "DDoS Attack Post-Mortem");
+        advance(&mut doc, &mut y, 28.0);
+        
+        let summary = [
+            ("Attack", self.attack_id.clone()),
+            ("Type", self.attack_type.clone()),
+            ("Target", self.target.clone()),
+            ("Started", self.start_time.to_rfc3339()),
+            ("Ended", self.end_time.map_or_else(|| "ongoing".to_string(), |t| t.to_rfc3339())),
+            ("Duration", self.duration_seconds.map_or_else(|| "-".to_string(), |d| format!("{}s", d))),
+            ("Peak", format!("{} pps / {} bps", self.peak_pps, self.peak_bps)),
+            ("Unique sources", self.unique_sources.to_string()),
+            ("Effectiveness", format!("{:.1}% of mitigated packets dropped", self.effectiveness * 100.0)),
+        ];
+        for (label, value) in summary {
+            doc.text(PDF_MARGIN, y, Font::Bold, 10.0, label);
+            doc.text(PDF_MARGIN + 110.0, y, Font::Regular, 10.0, &value);
+            advance(&mut doc, &mut y, 14.0);
+        }
+        
+        advance(&mut doc, &mut y, 16.0);
+        if y - CHART_HEIGHT - 40.0 < PDF_MARGIN {
+            doc.new_page();
+            y = PAGE_HEIGHT - PDF_MARGIN;
+        }
+        doc.text(PDF_MARGIN, y, Font::Bold, 12.0, "Traffic");
+        y -= 14.0;
+        self.draw_curve(&mut doc, y - CHART_HEIGHT, y);
+        y -= CHART_HEIGHT + 14.0;
+        advance(&mut doc, &mut y, 24.0);
+        
+        doc.text(PDF_MARGIN, y, Font::Bold, 12.0, "Timeline");
+        advance(&mut doc, &mut y, 16.0);
+        for event in &self.mitigation_timeline {
+            doc.text(PDF_MARGIN, y, Font::Regular, 9.0, &event.timestamp.format("%H:%M:%S").to_string());
+            doc.text(PDF_MARGIN + 50.0, y, Font::Bold, 9.0, &event.action);
+            doc.text(PDF_MARGIN + 160.0, y, Font::Regular, 9.0, &clip(&event.details, 70));
+            advance(&mut doc, &mut y, 12.0);
+        }
+        
+        if !self.top_sources.is_empty() {
+            advance(&mut doc, &mut y, 12.0);
+            doc.text(PDF_MARGIN, y, Font::Bold, 12.0, "Top Sources");
+            advance(&mut doc, &mut y, 16.0);
+            for source in &self.top_sources {
+                doc.text(PDF_MARGIN, y, Font::Regular, 9.0, &source.ip);
+                doc.text(PDF_MARGIN + 160.0, y, Font::Regular, 9.0, &format!("{} pps ({:.1}%)", source.pps, source.percent));
+                advance(&mut doc, &mut y, 12.0);
+            }
+        }
+        
+        doc.finish()
+    }
+    
+    /// Attack rate and dropped packets over time, scaled to the peak
+    fn draw_curve(&self, doc: &mut PdfDocument, bottom: f32, top: f32) {
+        let (left, right) = (PDF_MARGIN + 40.0, PAGE_WIDTH - PDF_MARGIN);
+        doc.line((left, bottom), (right, bottom), 0.5, Color::BLACK);
+        doc.line((left, bottom), (left, top), 0.5, Color::BLACK);
+        
+        let (Some(first), Some(last)) = (self.traffic_curve.first(), self.traffic_curve.last()) else {
+            doc.text(left + 10.0, (bottom + top) / 2.0, Font::Regular, 10.0, "No traffic recorded");
+            return;
+        };
+        
+        let bucket = self.traffic_curve.len().div_ceil(CHART_POINTS);
+        let buckets: Vec<(i64, u64, u64)> = self.traffic_curve.chunks(bucket)
+            .map(|chunk| (
+                (chunk[0].timestamp - first.timestamp).num_seconds(),
+                chunk.iter().map(|p| p.pps).max().unwrap_or(0),
+                chunk.iter().map(|p| p.packets_dropped).max().unwrap_or(0),
+            ))
+            .collect();
+        let span = (last.timestamp - first.timestamp).num_seconds().max(1) as f32;
+        let peak = buckets.iter().map(|b| b.1.max(b.2)).max().unwrap_or(0).max(1) as f32;
+        let x = |secs: i64| left + secs as f32 / span * (right - left);
+        let y = |value: u64| bottom + value as f32 / peak * (top - bottom);
+        
+        let attack: Vec<(f32, f32)> = buckets.iter().map(|b| (x(b.0), y(b.1))).collect();
+        let dropped: Vec<(f32, f32)> = buckets.iter().map(|b| (x(b.0), y(b.2))).collect();
+        doc.line((left, top), (right, top), 0.25, Color::GRAY);
+        doc.polyline(&attack, 1.0, ATTACK_COLOR);
+        doc.polyline(&dropped, 1.0, DROPPED_COLOR);
+        
+        doc.text(PDF_MARGIN - 10.0, top - 3.0, Font::Regular, 7.0, &format!("{:.0}", peak));
+        doc.text(PDF_MARGIN + 30.0, bottom - 3.0, Font::Regular, 7.0, "0");
+        doc.text(left, bottom - 12.0, Font::Regular, 7.0, &first.timestamp.format("%H:%M:%S").to_string());
+        doc.text(right - 30.0, bottom - 12.0, Font::Regular, 7.0, &last.timestamp.format("%H:%M:%S").to_string());
+        doc.line((left + 120.0, top + 6.0), (left + 140.0, top + 6.0), 1.0, ATTACK_COLOR);
+        doc.text(left + 144.0, top + 3.0, Font::Regular, 8.0, "packets/s");
+        doc.line((left + 200.0, top + 6.0), (left + 220.0, top + 6.0), 1.0, DROPPED_COLOR);
+        doc.text(left + 224.0, top + 3.0, Font::Regular, 8.0, "dropped/s");
+        if self.curve_truncated {
+            doc.text(left + 290.0, top + 3.0, Font::Regular, 8.0, "(earliest seconds not retained)");
+        }
+    }
+}
+
+/// Move down a line, starting a new page at the bottom margin
+fn advance(doc: &mut PdfDocument, y: &mut f32, step: f32) {
+    *y -= step;
+    if *y < PDF_MARGIN {
+        doc.new_page();
+        *y = PAGE_HEIGHT - PDF_MARGIN;
+    }
+}
+
+fn clip(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,442 @@
+//! Carpet-Bombing Detection
+//!
+//! Carpet-bombing spreads an attack across many addresses of a prefix so
+//! that no single destination crosses the per-destination thresholds. The
+//! aggregator views traffic per destination prefix (/24 and /64 by default)
+//! and per customer prefix group, and flags a window when the aggregate is
+//! large, touches many destinations, and no destination carries a dominant
+//! share of it.
+//!
+//! Attacks it reports target the prefix (`AttackTarget::prefix_len` is set),
+//! so mitigations are installed for the prefix rather than a single host.
+//! When two or more vectors each carry a meaningful share of the traffic the
+//! attack is classified [`AttackType::MultiVector`].
+
+use crate::{
+    Attack, AttackMetrics, AttackSource, AttackStatus, AttackTarget, AttackType,
+    DetectionConfig, Protocol, TrafficSample,
+};
+use dashmap::DashMap;
+use ipnetwork::IpNetwork;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Sources tracked per window
+const MAX_WINDOW_SOURCES: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarpetConfig {
+    /// Prefix length IPv4 destinations are grouped by
+    pub ipv4_prefix: u8,
+    /// Prefix length IPv6 destinations are grouped by
+    pub ipv6_prefix: u8,
+    /// Distinct destinations hit before an aggregate counts as carpet bombing
+    pub min_destinations: usize,
+    /// Largest share of the aggregate a single destination may carry
+    pub max_destination_share: f64,
+    /// Share of traffic a vector needs to count towards a multi-vector attack
+    pub multi_vector_share: f64,
+}
+
+impl Default for CarpetConfig {
+    fn default() -> Self {
+        Self {
+            ipv4_prefix: 24,
+            ipv6_prefix: 64,
+            min_destinations: 16,
+            max_destination_share: 0.25,
+            multi_vector_share: 0.2,
+        }
+    }
+}
+
+/// Prefixes belonging to one customer, aggregated together
+#[derive(Debug, Clone)]
+struct CustomerGroup {
+    customer_id: String,
+    prefixes: Vec<IpNetwork>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AggregateKey {
+    Prefix(IpNetwork),
+    Customer(String),
+}
+
+struct AggregateWindow {
+    started: Instant,
+    pps: u64,
+    bps: u64,
+    /// Rate per destination
+    destinations: HashMap<IpAddr, (u64, u64)>,
+    /// Rate per attack vector
+    vectors: HashMap<AttackType, u64>,
+    /// Rate per source
+    sources: HashMap<IpAddr, (u64, u64)>,
+    protocols: HashMap<Protocol, u64>,
+}
+
+impl AggregateWindow {
+    fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            pps: 0,
+            bps: 0,
+            destinations: HashMap::new(),
+            vectors: HashMap::new(),
+            sources: HashMap::new(),
+            protocols: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, sample: &TrafficSample) {
+        self.pps += sample.pps;
+        self.bps += sample.bps;
+
+        let destination = self.destinations.entry(sample.destination).or_default();
+        destination.0 += sample.pps;
+        destination.1 += sample.bps;
+
+        *self.vectors.entry(sample_vector(sample)).or_default() += sample.pps;
+        *self.protocols.entry(sample.protocol).or_default() += sample.pps;
+
+        if self.sources.len() < MAX_WINDOW_SOURCES || self.sources.contains_key(&sample.source) {
+            let source = self.sources.entry(sample.source).or_default();
+            source.0 += sample.pps;
+            source.1 += sample.bps;
+        }
+    }
+}
+
+/// Aggregates traffic over destination prefixes and customer groups
+pub struct PrefixAggregator {
+    config: CarpetConfig,
+    detection: DetectionConfig,
+    groups: RwLock<Vec<CustomerGroup>>,
+    windows: DashMap<AggregateKey, Mutex<AggregateWindow>>,
+    /// Recent attack fingerprints for dedup
+    recent_attacks: DashMap<String, Instant>,
+}
+
+impl PrefixAggregator {
+    pub fn new(config: CarpetConfig, detection: DetectionConfig) -> Self {
+        Self {
+            config,
+            detection,
+            groups: RwLock::new(Vec::new()),
+            windows: DashMap::new(),
+            recent_attacks: DashMap::new(),
+        }
+    }
+
+    /// Aggregate a customer's prefixes as one view; replaces earlier ones
+    pub fn add_customer_prefixes(&self, customer_id: &str, prefixes: Vec<IpNetwork>) {
+        let mut groups = self.groups.write();
+        groups.retain(|g| g.customer_id != customer_id);
+        groups.push(CustomerGroup { customer_id: customer_id.to_string(), prefixes });
+        self.windows.remove(&AggregateKey::Customer(customer_id.to_string()));
+    }
+
+    pub fn remove_customer(&self, customer_id: &str) -> bool {
+        let mut groups = self.groups.write();
+        let before = groups.len();
+        groups.retain(|g| g.customer_id != customer_id);
+        self.windows.remove(&AggregateKey::Customer(customer_id.to_string()));
+        groups.len() != before
+    }
+
+    /// Customer whose prefixes cover an address
+    pub fn customer_for(&self, ip: &IpAddr) -> Option<String> {
+        self.groups.read().iter()
+            .find(|g| g.prefixes.iter().any(|p| p.contains(*ip)))
+            .map(|g| g.customer_id.clone())
+    }
+
+    /// Account a sample and check any window it closes
+    ///
+    /// Customer views are checked before the prefix view, so an attack on a
+    /// customer is reported with the customer attached.
+    pub fn observe(&self, sample: &TrafficSample) -> Option<Attack> {
+        let now = Instant::now();
+        let window = Duration::from_millis(self.detection.detection_window_ms);
+        let customer = self.customer_for(&sample.destination);
+
+        let mut keys = Vec::with_capacity(2);
+        if let Some(customer_id) = &customer {
+            keys.push(AggregateKey::Customer(customer_id.clone()));
+        }
+        keys.push(AggregateKey::Prefix(self.prefix_of(sample.destination)));
+
+        let mut detected = None;
+        for key in keys {
+            let entry = self.windows.entry(key.clone())
+                .or_insert_with(|| Mutex::new(AggregateWindow::new(now)));
+            let mut current = entry.lock();
+            current.add(sample);
+            if now.duration_since(current.started) < window {
+                continue;
+            }
+
+            let closed = std::mem::replace(&mut *current, AggregateWindow::new(now));
+            drop(current);
+            drop(entry);
+            if detected.is_none() {
+                detected = self.detect(&key, closed, customer.clone());
+            }
+        }
+        detected
+    }
+
+    /// Drop windows that have gone quiet and expired fingerprints
+    pub fn prune(&self, idle: Duration) {
+        self.windows.retain(|_, w| w.lock().started.elapsed() < idle);
+        let cooldown = Duration::from_secs(self.detection.cooldown_seconds);
+        self.recent_attacks.retain(|_, seen| seen.elapsed() < cooldown);
+    }
+
+    fn prefix_of(&self, ip: IpAddr) -> IpNetwork {
+        let len = if ip.is_ipv4() { self.config.ipv4_prefix } else { self.config.ipv6_prefix };
+        IpNetwork::new(ip, len)
+            .and_then(|n| IpNetwork::new(n.network(), len))
+            .unwrap_or_else(|_| IpNetwork::from(ip))
+    }
+
+    fn detect(&self, key: &AggregateKey, window: AggregateWindow, customer: Option<String>) -> Option<Attack> {
+        if window.pps < self.detection.min_pps_threshold
+            || window.destinations.len() < self.config.min_destinations
+        {
+            return None;
+        }
+
+        // A dominant destination is the per-destination detector's business
+        let busiest = window.destinations.values().map(|(pps, _)| *pps).max().unwrap_or(0);
+        if busiest as f64 > window.pps as f64 * self.config.max_destination_share {
+            return None;
+        }
+
+        let target = match key {
+            AggregateKey::Prefix(prefix) => *prefix,
+            AggregateKey::Customer(_) => self.busiest_customer_prefix(customer.as_deref()?, &window)?,
+        };
+        let attack_type = self.classify(&window);
+
+        let fingerprint = format!("{}-{:?}", target, attack_type);
+        if let Some(last_seen) = self.recent_attacks.get(&fingerprint) {
+            if last_seen.elapsed().as_secs() < self.detection.cooldown_seconds {
+                return None;
+            }
+        }
+        self.recent_attacks.insert(fingerprint, Instant::now());
+
+        tracing::warn!(
+            "Carpet-bombing {:?} across {} destinations in {} ({} pps)",
+            attack_type, window.destinations.len(), target, window.pps
+        );
+        Some(self.build_attack(target, attack_type, customer, window))
+    }
+
+    /// Customer prefix most of the window's destinations fall in
+    fn busiest_customer_prefix(&self, customer_id: &str, window: &AggregateWindow) -> Option<IpNetwork> {
+        let groups = self.groups.read();
+        let group = groups.iter().find(|g| g.customer_id == customer_id)?;
+        group.prefixes.iter()
+            .max_by_key(|p| window.destinations.keys().filter(|ip| p.contains(**ip)).count())
+            .copied()
+    }
+
+    fn classify(&self, window: &AggregateWindow) -> AttackType {
+        let total = window.pps.max(1) as f64;
+        let mut vectors: Vec<(AttackType, u64)> = window.vectors.iter()
+            .filter(|(vector, _)| **vector != AttackType::Unknown)
+            .map(|(vector, pps)| (*vector, *pps))
+            .collect();
+        vectors.sort_by_key(|(_, pps)| std::cmp::Reverse(*pps));
+
+        let significant = vectors.iter()
+            .filter(|(_, pps)| *pps as f64 / total >= self.config.multi_vector_share)
+            .count();
+        match (significant, vectors.first()) {
+            (n, _) if n >= 2 => AttackType::MultiVector,
+            (_, Some((vector, _))) => *vector,
+            _ => AttackType::Unknown,
+        }
+    }
+
+    fn build_attack(
+        &self,
+        target: IpNetwork,
+        attack_type: AttackType,
+        customer_id: Option<String>,
+        window: AggregateWindow,
+    ) -> Attack {
+        let mut sources: Vec<AttackSource> = window.sources.iter()
+            .map(|(ip, (pps, bps))| AttackSource {
+                ip: *ip,
+                network: None,
+                asn: None,
+                country: None,
+                pps: *pps,
+                bps: *bps,
+                is_spoofed: false,
+            })
+            .collect();
+        sources.sort_by_key(|s| std::cmp::Reverse(s.pps));
+        sources.truncate(10);
+
+        let total = window.pps.max(1) as f64;
+        let protocol = window.protocols.iter()
+            .max_by_key(|(_, pps)| **pps)
+            .map(|(protocol, _)| *protocol)
+            .unwrap_or(Protocol::Other(0));
+
+        Attack {
+            id: uuid::Uuid::new_v4().to_string(),
+            attack_type,
+            target: AttackTarget {
+                ip: target.network(),
+                port: None,
+                protocol,
+                customer_id,
+                prefix_len: Some(target.prefix()),
+            },
+            sources,
+            metrics: AttackMetrics {
+                total_pps: window.pps,
+                total_bps: window.bps,
+                peak_pps: window.pps,
+                peak_bps: window.bps,
+                unique_sources: window.sources.len() as u64,
+                avg_packet_size: window.bps.checked_div(window.pps).map_or(0, |bits| (bits / 8) as u32),
+                protocol_distribution: window.protocols.iter()
+                    .map(|(protocol, pps)| (*protocol, *pps as f64 / total))
+                    .collect(),
+            },
+            started_at: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            status: AttackStatus::Detected,
+            mitigation: None,
+        }
+    }
+}
+
+/// Attack vector a single sample belongs to
+fn sample_vector(sample: &TrafficSample) -> AttackType {
+    match sample.protocol {
+        Protocol::Tcp => match sample.tcp_flags {
+            Some(flags) if flags & 0x04 != 0 => AttackType::RstFlood,
+            Some(flags) if flags & 0x02 != 0 && flags & 0x10 == 0 => AttackType::SynFlood,
+            Some(flags) if flags & 0x10 != 0 => AttackType::AckFlood,
+            _ => AttackType::Unknown,
+        },
+        // Reflected traffic arrives from the amplifying service's port
+        Protocol::Udp => match sample.src_port {
+            19 => AttackType::ChargenAmplification,
+            53 => AttackType::DnsAmplification,
+            123 => AttackType::NtpAmplification,
+            1900 => AttackType::SsdpAmplification,
+            11211 => AttackType::MemcachedAmplification,
+            _ => AttackType::UdpFlood,
+        },
+        Protocol::Icmp => AttackType::IcmpFlood,
+        _ => AttackType::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(destination: IpAddr, protocol: Protocol, src_port: u16, tcp_flags: Option<u8>) -> TrafficSample {
+        TrafficSample {
+            timestamp: Instant::now(),
+            source: "198.51.100.7".parse().unwrap(),
+            destination,
+            protocol,
+            src_port,
+            dst_port: 443,
+            packet_size: 512,
+            tcp_flags,
+            pps: 10_000,
+            bps: 40_000_000,
+        }
+    }
+
+    fn aggregator() -> PrefixAggregator {
+        let detection = DetectionConfig { detection_window_ms: 0, ..Default::default() };
+        PrefixAggregator::new(CarpetConfig::default(), detection)
+    }
+
+    /// Spread samples over hosts .1-.32, alternating vectors, then close
+    fn carpet(aggregator: &PrefixAggregator) -> Option<Attack> {
+        let mut detected = None;
+        for host in 1..=32u8 {
+            let destination = IpAddr::from([203, 0, 113, host]);
+            let s = if host % 2 == 0 {
+                sample(destination, Protocol::Udp, 53, None)
+            } else {
+                sample(destination, Protocol::Tcp, 40000, Some(0x02))
+            };
+            // Zero-length windows close on every sample; keep them open
+            // until the whole carpet is in
+            let key = AggregateKey::Prefix(aggregator.prefix_of(destination));
+            aggregator.windows.entry(key)
+                .or_insert_with(|| Mutex::new(AggregateWindow::new(Instant::now())))
+                .lock()
+                .add(&s);
+            if host == 32 {
+                detected = aggregator.observe(&sample(destination, Protocol::Icmp, 0, None));
+            }
+        }
+        detected
+    }
+
+    #[test]
+    fn test_carpet_bombing_detected_on_prefix() {
+        let aggregator = aggregator();
+        let attack = carpet(&aggregator).expect("carpet bombing detected");
+
+        assert_eq!(attack.attack_type, AttackType::MultiVector);
+        assert_eq!(attack.target.cidr().as_deref(), Some("203.0.113.0/24"));
+        assert!(attack.target.covers(&"203.0.113.200".parse().unwrap()));
+        assert!(attack.target.customer_id.is_none());
+    }
+
+    #[test]
+    fn test_customer_group_attribution() {
+        let aggregator = aggregator();
+        aggregator.add_customer_prefixes("acme", vec!["203.0.113.0/25".parse().unwrap()]);
+        // The customer window needs the same traffic as the prefix window
+        for host in 1..=31u8 {
+            let destination = IpAddr::from([203, 0, 113, host]);
+            aggregator.windows.entry(AggregateKey::Customer("acme".to_string()))
+                .or_insert_with(|| Mutex::new(AggregateWindow::new(Instant::now())))
+                .lock()
+                .add(&sample(destination, Protocol::Udp, 123, None));
+        }
+        let attack = aggregator.observe(&sample("203.0.113.32".parse().unwrap(), Protocol::Udp, 123, None))
+            .expect("attack on customer detected");
+
+        assert_eq!(attack.attack_type, AttackType::NtpAmplification);
+        assert_eq!(attack.target.customer_id.as_deref(), Some("acme"));
+        assert_eq!(attack.target.cidr().as_deref(), Some("203.0.113.0/25"));
+    }
+
+    #[test]
+    fn test_single_hot_destination_left_to_host_detector() {
+        let aggregator = aggregator();
+        let hot: IpAddr = "203.0.113.1".parse().unwrap();
+        let mut s = sample(hot, Protocol::Udp, 53, None);
+        s.pps = 1_000_000;
+        let key = AggregateKey::Prefix(aggregator.prefix_of(hot));
+        aggregator.windows.insert(key.clone(), Mutex::new(AggregateWindow::new(Instant::now())));
+        for host in 2..=32u8 {
+            aggregator.windows.get(&key).unwrap()
+                .lock()
+                .add(&sample(IpAddr::from([203, 0, 113, host]), Protocol::Udp, 53, None));
+        }
+        assert!(aggregator.observe(&s).is_none());
+    }
+}
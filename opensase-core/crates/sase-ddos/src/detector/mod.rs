@@ -1,7 +1,16 @@
 //! Attack Detection Engine
 //!
 //! <100μs detection latency with multi-stage analysis.
+//!
+//! Besides the per-destination windows, an optional [`PrefixAggregator`]
+//! watches destination prefixes and customer prefix groups for attacks
+//! spread too thinly to trip any single destination.
+
+pub mod carpet;
+
+pub use carpet::{CarpetConfig, PrefixAggregator};
 
+use crate::baseline::BaselineLearner;
 use crate::{
     Attack, AttackMetrics, AttackSource, AttackStatus, AttackTarget, AttackType,
     DetectionConfig, Protocol, TrafficBaseline, TrafficSample,
@@ -10,6 +19,7 @@ use dashmap::DashMap;
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// Attack detector with line-rate analysis
@@ -21,6 +31,10 @@ pub struct AttackDetector {
     source_stats: DashMap<IpAddr, SourceStats>,
     /// Recent attack fingerprints for dedup
     recent_attacks: DashMap<String, Instant>,
+    /// Learned baselines; preferred over static ones when mature
+    learner: Option<Arc<BaselineLearner>>,
+    /// Prefix and customer-group views for carpet bombing
+    aggregator: Option<Arc<PrefixAggregator>>,
     /// Global counters
     total_samples: AtomicU64,
     total_attacks: AtomicU64,
@@ -30,13 +44,16 @@ pub struct AttackDetector {
 struct DestinationStats {
     pps: AtomicU64,
     bps: AtomicU64,
+    samples: AtomicU64,
     syn_count: AtomicU64,
     ack_count: AtomicU64,
     udp_count: AtomicU64,
     icmp_count: AtomicU64,
     unique_sources: AtomicU64,
-    last_window_start: parking_lot::Mutex<Instant>,
+    /// Unset until the first sample opens a window
+    last_window_start: parking_lot::Mutex<Option<Instant>>,
     source_ips: parking_lot::Mutex<Vec<IpAddr>>,
+    protocol_samples: parking_lot::Mutex<HashMap<Protocol, u64>>,
 }
 
 #[derive(Default)]
@@ -54,11 +71,25 @@ impl AttackDetector {
             destination_stats: DashMap::new(),
             source_stats: DashMap::new(),
             recent_attacks: DashMap::new(),
+            learner: None,
+            aggregator: None,
             total_samples: AtomicU64::new(0),
             total_attacks: AtomicU64::new(0),
         }
     }
     
+    /// Score windows against learned baselines
+    pub fn with_learner(mut self, learner: Arc<BaselineLearner>) -> Self {
+        self.learner = Some(learner);
+        self
+    }
+    
+    /// Also detect attacks spread across prefixes and customer groups
+    pub fn with_aggregator(mut self, aggregator: Arc<PrefixAggregator>) -> Self {
+        self.aggregator = Some(aggregator);
+        self
+    }
+    
     /// Analyze traffic sample for attacks
     pub async fn analyze(
         &self,
@@ -70,6 +101,12 @@ impl AttackDetector {
         // Update counters
         self.update_stats(sample);
         
+        // Aggregate views; an attack on a prefix outranks one on a host
+        let aggregate = self.aggregator.as_ref().and_then(|a| a.observe(sample));
+        if aggregate.is_some() {
+            self.total_attacks.fetch_add(1, Ordering::Relaxed);
+        }
+        
         // Get or create destination stats
         let dest_stats = self.destination_stats
             .entry(sample.destination)
@@ -81,23 +118,24 @@ impl AttackDetector {
         
         {
             let mut last_start = dest_stats.last_window_start.lock();
-            if now.duration_since(*last_start).as_millis() as u64 >= window_ms {
+            let window_start = *last_start.get_or_insert(now);
+            if now.duration_since(window_start).as_millis() as u64 >= window_ms {
                 // New window - check for attacks
                 let attack = self.detect_in_window(&dest_stats, sample, baseline);
                 
                 // Reset window
-                *last_start = now;
+                *last_start = Some(now);
                 self.reset_window_stats(&dest_stats);
                 
-                if attack.is_some() {
+                if attack.is_some() && aggregate.is_none() {
                     self.total_attacks.fetch_add(1, Ordering::Relaxed);
                 }
                 
-                return attack;
+                return aggregate.or(attack);
             }
         }
         
-        None
+        aggregate
     }
     
     fn update_stats(&self, sample: &TrafficSample) {
@@ -108,6 +146,8 @@ impl AttackDetector {
         
         dest.pps.fetch_add(sample.pps, Ordering::Relaxed);
         dest.bps.fetch_add(sample.bps, Ordering::Relaxed);
+        dest.samples.fetch_add(1, Ordering::Relaxed);
+        *dest.protocol_samples.lock().entry(sample.protocol).or_default() += 1;
         
         // Track protocol
         match sample.protocol {
@@ -169,8 +209,25 @@ impl AttackDetector {
             return None;
         }
         
-        // Check against baseline
-        if let Some(bl) = baseline {
+        // Check against the learned baseline, falling back to a static one.
+        // Samples carry the destination's rate when taken, so the window's
+        // mean rate is what compares with the learned one.
+        let samples = stats.samples.load(Ordering::Relaxed).max(1) as f64;
+        let mix: HashMap<Protocol, f64> = stats.protocol_samples.lock().iter()
+            .map(|(protocol, count)| (*protocol, *count as f64))
+            .collect();
+        let score = self.learner.as_ref().and_then(|learner| {
+            learner.score(&sample.destination, pps as f64 / samples, bps as f64 / samples, &mix, chrono::Utc::now())
+        });
+        if let Some(score) = score {
+            if score.score < self.config.anomaly_score_threshold {
+                return None;
+            }
+            tracing::debug!(
+                "Anomaly score {:.1} for {} (pps z={:.1}, bps z={:.1}, mix shift={:.2}, seasonal={})",
+                score.score, sample.destination, score.pps_z, score.bps_z, score.mix_shift, score.seasonal
+            );
+        } else if let Some(bl) = baseline {
             if !bl.is_anomaly(pps, bps, self.config.anomaly_threshold) {
                 return None;
             }
@@ -199,6 +256,7 @@ impl AttackDetector {
                 port: Some(sample.dst_port),
                 protocol: sample.protocol,
                 customer_id: None,
+                prefix_len: None,
             },
             sources,
             metrics: AttackMetrics {
@@ -281,12 +339,14 @@ impl AttackDetector {
     fn reset_window_stats(&self, stats: &DestinationStats) {
         stats.pps.store(0, Ordering::Relaxed);
         stats.bps.store(0, Ordering::Relaxed);
+        stats.samples.store(0, Ordering::Relaxed);
         stats.syn_count.store(0, Ordering::Relaxed);
         stats.ack_count.store(0, Ordering::Relaxed);
         stats.udp_count.store(0, Ordering::Relaxed);
         stats.icmp_count.store(0, Ordering::Relaxed);
         stats.unique_sources.store(0, Ordering::Relaxed);
         stats.source_ips.lock().clear();
+        stats.protocol_samples.lock().clear();
     }
     
     /// Calculate entropy of source IP distribution
@@ -0,0 +1,277 @@
+//! BIRD Flowspec Configuration
+//!
+//! Renders announced rules as static `flow4`/`flow6` routes and the BGP
+//! sessions that carry them upstream. The output is a self-contained file
+//! the main `bird.conf` includes; BIRD withdraws anything that disappears
+//! from it on the next `configure`.
+
+use super::controller::{FlowspecConfig, FlowspecDriver, PeerStatus};
+use super::{FlowspecRule, FragmentType};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Prefix for generated protocol names, so status parsing can find them
+pub const PROTOCOL_PREFIX: &str = "ddos_fs_";
+
+const TABLE_V4: &str = "ddos_flow4";
+const TABLE_V6: &str = "ddos_flow6";
+
+/// Upstream router receiving Flowspec announcements
+#[derive(Debug, Clone)]
+pub struct FlowspecPeer {
+    /// Short name; becomes part of the BIRD protocol name
+    pub name: String,
+    pub neighbor: IpAddr,
+    pub remote_asn: u32,
+    /// Source address for the session; BIRD picks one when unset
+    pub local_address: Option<IpAddr>,
+    /// TCP MD5 password
+    pub password: Option<String>,
+    /// Carry IPv6 Flowspec (RFC 8956) as well as IPv4
+    pub ipv6: bool,
+}
+
+impl FlowspecPeer {
+    pub fn new(name: &str, neighbor: IpAddr, remote_asn: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            neighbor,
+            remote_asn,
+            local_address: None,
+            password: None,
+            ipv6: true,
+        }
+    }
+
+    pub fn with_local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    pub fn with_password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// BIRD protocol name for this session
+    pub fn protocol_name(&self) -> String {
+        format!("{}{}", PROTOCOL_PREFIX, sanitize(&self.name))
+    }
+}
+
+impl FlowspecRule {
+    /// Render as a BIRD static flow route, action as extended communities
+    pub fn to_bird_route(&self, local_asn: u32) -> String {
+        let mut out = String::new();
+        let family = if self.destination.is_ipv4() { "flow4" } else { "flow6" };
+
+        let _ = writeln!(out, "    route {} {{", family);
+        let _ = writeln!(out, "        dst {}/{};", self.destination, self.destination_prefix);
+        if let Some(src) = self.source {
+            let _ = writeln!(out, "        src {}/{};", src, self.source_prefix);
+        }
+        if let Some(proto) = &self.protocol {
+            // flow6 calls the protocol field next-header
+            let field = if self.destination.is_ipv4() { "proto" } else { "next header" };
+            let _ = writeln!(out, "        {} = {};", field, super::protocol_num(proto));
+        }
+        if let Some(port) = self.destination_port {
+            let _ = writeln!(out, "        dport = {};", port);
+        }
+        if let Some(port) = self.source_port {
+            let _ = writeln!(out, "        sport = {};", port);
+        }
+        if let Some(flags) = self.tcp_flags {
+            let _ = writeln!(out, "        tcp flags 0x{:02x}/0x{:02x};", flags, flags);
+        }
+        if let Some((min, max)) = self.packet_length {
+            if min == max {
+                let _ = writeln!(out, "        length = {};", min);
+            } else {
+                let _ = writeln!(out, "        length >= {} && <= {};", min, max);
+            }
+        }
+        if let Some(dscp) = self.dscp {
+            let _ = writeln!(out, "        dscp = {};", dscp & 0x3f);
+        }
+        if let Some(fragment) = self.fragment {
+            let _ = writeln!(out, "        fragment {};", fragment.bird_keyword());
+        }
+        out.push_str("    } {\n");
+
+        for community in self.extended_communities(local_asn) {
+            let high = u32::from_be_bytes([community[0], community[1], community[2], community[3]]);
+            let low = u32::from_be_bytes([community[4], community[5], community[6], community[7]]);
+            let _ = writeln!(out, "        bgp_ext_community.add((generic, 0x{:08x}, 0x{:08x}));", high, low);
+        }
+        out.push_str("    };\n");
+        out
+    }
+}
+
+impl FragmentType {
+    fn bird_keyword(&self) -> &'static str {
+        match self {
+            Self::NotFragment => "dont_fragment",
+            Self::IsFragment => "is_fragment",
+            Self::FirstFragment => "first_fragment",
+            Self::LastFragment => "last_fragment",
+        }
+    }
+}
+
+/// Render the complete include file: tables, static routes, sessions
+pub fn render_config<'a>(
+    local_asn: u32,
+    peers: &[FlowspecPeer],
+    rules: impl IntoIterator<Item = &'a FlowspecRule>,
+) -> String {
+    let (v4, v6): (Vec<&FlowspecRule>, Vec<&FlowspecRule>) =
+        rules.into_iter().partition(|r| r.destination.is_ipv4());
+
+    let mut out = String::new();
+    out.push_str("# Generated by sase-ddos; changes are overwritten.\n\n");
+    let _ = writeln!(out, "flow4 table {};", TABLE_V4);
+    let _ = writeln!(out, "flow6 table {};\n", TABLE_V6);
+
+    for (family, table, routes) in [("flow4", TABLE_V4, &v4), ("flow6", TABLE_V6, &v6)] {
+        let _ = writeln!(out, "protocol static {}{} {{", PROTOCOL_PREFIX, family);
+        let _ = writeln!(out, "    {} {{ table {}; }};", family, table);
+        for rule in routes.iter() {
+            out.push_str(&rule.to_bird_route(local_asn));
+        }
+        out.push_str("}\n\n");
+    }
+
+    for peer in peers {
+        let _ = writeln!(out, "protocol bgp {} {{", peer.protocol_name());
+        let _ = writeln!(out, "    description \"DDoS Flowspec to {}\";", peer.name.replace('"', ""));
+        match peer.local_address {
+            Some(addr) => { let _ = writeln!(out, "    local {} as {};", addr, local_asn); }
+            None => { let _ = writeln!(out, "    local as {};", local_asn); }
+        }
+        let _ = writeln!(out, "    neighbor {} as {};", peer.neighbor, peer.remote_asn);
+        if let Some(password) = &peer.password {
+            let _ = writeln!(out, "    password \"{}\";", password.replace('"', ""));
+        }
+        // Announce only; never learn Flowspec from upstream
+        let _ = writeln!(out, "    flow4 {{ table {}; import none; export all; }};", TABLE_V4);
+        if peer.ipv6 {
+            let _ = writeln!(out, "    flow6 {{ table {}; import none; export all; }};", TABLE_V6);
+        }
+        out.push_str("}\n\n");
+    }
+
+    out
+}
+
+/// Protocol names may only contain letters, digits and underscores
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// =============================================================================
+// Driver
+// =============================================================================
+
+/// Announces by rewriting the include file and reconfiguring BIRD
+pub struct BirdDriver {
+    local_asn: u32,
+    config_path: PathBuf,
+    bird_socket: String,
+    peers: Vec<FlowspecPeer>,
+}
+
+impl BirdDriver {
+    pub fn new(config: &FlowspecConfig) -> Self {
+        Self {
+            local_asn: config.local_asn,
+            config_path: config.config_path.clone(),
+            bird_socket: config.bird_socket.clone(),
+            peers: config.peers.clone(),
+        }
+    }
+
+    async fn birdc(&self, args: &[&str]) -> Result<String, String> {
+        use tokio::process::Command;
+
+        let output = Command::new("birdc")
+            .arg("-s")
+            .arg(&self.bird_socket)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| format!("birdc failed: {}", e))?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+#[async_trait::async_trait]
+impl FlowspecDriver for BirdDriver {
+    /// Render the include file and reload BIRD. The previous file is
+    /// restored if BIRD rejects the new one.
+    async fn apply(&self, rules: &[FlowspecRule]) -> Result<(), String> {
+        let rendered = render_config(self.local_asn, &self.peers, rules);
+
+        let path = &self.config_path;
+        let previous = tokio::fs::read_to_string(path).await.ok();
+        if previous.as_deref() == Some(rendered.as_str()) {
+            return Ok(());
+        }
+
+        write_atomic(path, &rendered).await?;
+
+        let check = self.birdc(&["configure", "check"]).await?;
+        if !check.contains("Configuration OK") {
+            match &previous {
+                Some(content) => write_atomic(path, content).await?,
+                None => { let _ = tokio::fs::remove_file(path).await; }
+            }
+            return Err(format!("BIRD rejected Flowspec config: {}", check.trim()));
+        }
+
+        let reply = self.birdc(&["configure"]).await?;
+        if !reply.contains("Reconfigur") {
+            return Err(format!("BIRD reconfigure failed: {}", reply.trim()));
+        }
+        Ok(())
+    }
+
+    async fn peer_status(&self) -> Result<Vec<PeerStatus>, String> {
+        let output = self.birdc(&["show", "protocols"]).await?;
+        Ok(parse_protocols(&output))
+    }
+}
+
+/// Write aside and rename so BIRD never reads a half-written file
+async fn write_atomic(path: &std::path::Path, content: &str) -> Result<(), String> {
+    let partial = path.with_extension("partial");
+    tokio::fs::write(&partial, content).await
+        .map_err(|e| format!("{}: {}", partial.display(), e))?;
+    tokio::fs::rename(&partial, path).await
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Pick our BGP sessions out of `show protocols`:
+/// `name  proto  table  state  since  info...`
+fn parse_protocols(output: &str) -> Vec<PeerStatus> {
+    output.lines()
+        .filter(|l| l.starts_with(PROTOCOL_PREFIX))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 || fields[1] != "BGP" {
+                return None;
+            }
+            Some(PeerStatus {
+                protocol: fields[0].to_string(),
+                state: fields[3].to_string(),
+                since: fields[4].to_string(),
+                info: fields[5..].join(" "),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,293 @@
+//! Flowspec Announcement Controller
+//!
+//! Tracks which rules are announced on behalf of which mitigation and
+//! keeps the BGP speaker in step with that set through a driver: BIRD
+//! (generated include file and reload) by default, or GoBGP over its gRPC
+//! API. Rules are withdrawn when their mitigation ends or when they expire,
+//! whichever comes first.
+
+use super::bird::{BirdDriver, FlowspecPeer};
+use super::{FlowspecAction, FlowspecRule};
+use crate::{MitigationRule, RuleAction};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Flowspec controller configuration
+#[derive(Debug, Clone)]
+pub struct FlowspecConfig {
+    pub local_asn: u32,
+    /// Generated BIRD include file
+    pub config_path: PathBuf,
+    /// BIRD control socket
+    pub bird_socket: String,
+    /// Upstream routers
+    pub peers: Vec<FlowspecPeer>,
+    /// Route target of the scrubbing VRF, for redirect actions
+    pub scrubbing_route_target: Option<String>,
+    /// Lifetime of an announcement without an explicit expiry
+    pub default_ttl: chrono::Duration,
+}
+
+impl FlowspecConfig {
+    pub fn new(local_asn: u32) -> Self {
+        Self {
+            local_asn,
+            config_path: PathBuf::from("/etc/bird/ddos-flowspec.conf"),
+            bird_socket: "/run/bird/bird.ctl".to_string(),
+            peers: Vec::new(),
+            scrubbing_route_target: None,
+            default_ttl: chrono::Duration::hours(1),
+        }
+    }
+
+    pub fn with_peer(mut self, peer: FlowspecPeer) -> Self {
+        self.peers.push(peer);
+        self
+    }
+
+    pub fn with_scrubbing_route_target(mut self, rt: &str) -> Self {
+        self.scrubbing_route_target = Some(rt.to_string());
+        self
+    }
+}
+
+/// A rule announced for a mitigation
+#[derive(Debug, Clone)]
+pub struct Announcement {
+    pub mitigation_id: String,
+    pub rule: FlowspecRule,
+    pub announced_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// BGP session state as BIRD reports it
+#[derive(Debug, Clone)]
+pub struct PeerStatus {
+    pub protocol: String,
+    pub state: String,
+    pub since: String,
+    pub info: String,
+}
+
+impl PeerStatus {
+    pub fn is_established(&self) -> bool {
+        self.info.starts_with("Established")
+    }
+}
+
+/// BGP speaker that carries the announcements upstream
+#[async_trait::async_trait]
+pub trait FlowspecDriver: Send + Sync {
+    /// Make the speaker announce exactly `rules`, withdrawing the rest
+    async fn apply(&self, rules: &[FlowspecRule]) -> Result<(), String>;
+    /// State of the upstream sessions
+    async fn peer_status(&self) -> Result<Vec<PeerStatus>, String>;
+}
+
+/// Announces Flowspec rules upstream through a BGP speaker
+pub struct FlowspecController {
+    config: FlowspecConfig,
+    driver: Arc<dyn FlowspecDriver>,
+    /// Announcements keyed by NLRI hex; one owner per match
+    announcements: parking_lot::RwLock<HashMap<String, Announcement>>,
+    /// Serialises driver updates
+    sync_lock: tokio::sync::Mutex<()>,
+}
+
+impl FlowspecController {
+    /// Controller announcing through BIRD
+    pub fn new(config: FlowspecConfig) -> Self {
+        Self {
+            driver: Arc::new(BirdDriver::new(&config)),
+            config,
+            announcements: parking_lot::RwLock::new(HashMap::new()),
+            sync_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Announce through another speaker, e.g. [`GoBgpDriver`](super::gobgp::GoBgpDriver)
+    pub fn with_driver(mut self, driver: Arc<dyn FlowspecDriver>) -> Self {
+        self.driver = driver;
+        self
+    }
+
+    pub fn config(&self) -> &FlowspecConfig {
+        &self.config
+    }
+
+    /// Announce rules for a mitigation. A rule whose match is already
+    /// announced moves to this mitigation with the new action.
+    pub async fn announce(
+        &self,
+        mitigation_id: &str,
+        rules: Vec<FlowspecRule>,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<usize, String> {
+        let now = chrono::Utc::now();
+        let expires_at = expires_at.unwrap_or(now + self.config.default_ttl);
+        let count = rules.len();
+
+        {
+            let mut announcements = self.announcements.write();
+            for rule in rules {
+                announcements.insert(rule.nlri_hex(), Announcement {
+                    mitigation_id: mitigation_id.to_string(),
+                    rule,
+                    announced_at: now,
+                    expires_at,
+                });
+            }
+        }
+
+        self.sync().await?;
+        info!("Announced {} Flowspec rules for mitigation {}", count, mitigation_id);
+        Ok(count)
+    }
+
+    /// Translate mitigation rules and announce those that map to Flowspec
+    pub async fn announce_mitigation(
+        &self,
+        mitigation_id: &str,
+        rules: &[MitigationRule],
+    ) -> Result<usize, String> {
+        let expires_at = rules.iter().filter_map(|r| r.expires_at).max();
+        let flows: Vec<FlowspecRule> = rules.iter().filter_map(|r| self.translate(r)).collect();
+        if flows.is_empty() {
+            return Ok(0);
+        }
+        self.announce(mitigation_id, flows, expires_at).await
+    }
+
+    /// Withdraw everything announced for a mitigation
+    pub async fn withdraw(&self, mitigation_id: &str) -> Result<usize, String> {
+        let removed = {
+            let mut announcements = self.announcements.write();
+            let before = announcements.len();
+            announcements.retain(|_, a| a.mitigation_id != mitigation_id);
+            before - announcements.len()
+        };
+
+        if removed > 0 {
+            self.sync().await?;
+            info!("Withdrew {} Flowspec rules for mitigation {}", removed, mitigation_id);
+        }
+        Ok(removed)
+    }
+
+    /// Withdraw announcements past their expiry
+    pub async fn withdraw_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize, String> {
+        let removed = {
+            let mut announcements = self.announcements.write();
+            let before = announcements.len();
+            announcements.retain(|_, a| a.expires_at > now);
+            before - announcements.len()
+        };
+
+        if removed > 0 {
+            self.sync().await?;
+            info!("Withdrew {} expired Flowspec rules", removed);
+        }
+        Ok(removed)
+    }
+
+    pub fn announcements(&self) -> Vec<Announcement> {
+        self.announcements.read().values().cloned().collect()
+    }
+
+    /// Map a mitigation rule onto a Flowspec rule; `None` for rules that
+    /// have no upstream equivalent
+    pub fn translate(&self, rule: &MitigationRule) -> Option<FlowspecRule> {
+        let destination = rule.destination?;
+        let host_prefix = if destination.is_ipv4() { 32 } else { 128 };
+        // Prefix-level rules match the whole attacked range
+        let (destination, destination_prefix) = match &rule.destination_prefix {
+            Some(cidr) => {
+                let network: ipnetwork::IpNetwork = cidr.parse().ok()?;
+                (network.network(), network.prefix())
+            }
+            None => (destination, host_prefix),
+        };
+
+        let (source, source_prefix) = match (&rule.source_prefix, rule.source) {
+            (Some(cidr), _) => {
+                let network: ipnetwork::IpNetwork = cidr.parse().ok()?;
+                (Some(network.network()), network.prefix())
+            }
+            (None, Some(ip)) => (Some(ip), if ip.is_ipv4() { 32 } else { 128 }),
+            (None, None) => (None, host_prefix),
+        };
+        // Flowspec cannot mix address families within one rule
+        if source.is_some_and(|s| s.is_ipv4() != destination.is_ipv4()) {
+            return None;
+        }
+
+        let action = match rule.action {
+            RuleAction::Drop => FlowspecAction::Drop,
+            RuleAction::RateLimit => {
+                let limit = rule.rate_limit.as_ref()?;
+                match (limit.bps, limit.pps) {
+                    // Traffic-rate is bytes per second
+                    (Some(bps), _) => FlowspecAction::RateLimit(bps / 8),
+                    (None, Some(pps)) => FlowspecAction::RateLimitPackets(pps),
+                    (None, None) => return None,
+                }
+            }
+            RuleAction::Redirect => FlowspecAction::Redirect(self.config.scrubbing_route_target.clone()?),
+            _ => return None,
+        };
+
+        Some(FlowspecRule {
+            destination,
+            destination_prefix,
+            source,
+            source_prefix,
+            protocol: rule.protocol,
+            source_port: None,
+            destination_port: rule.port,
+            tcp_flags: None,
+            packet_length: None,
+            dscp: None,
+            fragment: None,
+            action,
+        })
+    }
+
+    /// Push the current announcements to the speaker
+    pub async fn sync(&self) -> Result<(), String> {
+        let _guard = self.sync_lock.lock().await;
+
+        let rules = {
+            let announcements = self.announcements.read();
+            let mut rules: Vec<(String, FlowspecRule)> = announcements.iter()
+                .map(|(nlri, a)| (nlri.clone(), a.rule.clone()))
+                .collect();
+            // Stable order so unchanged sets don't churn the speaker
+            rules.sort_by(|a, b| a.0.cmp(&b.0));
+            rules.into_iter().map(|(_, rule)| rule).collect::<Vec<_>>()
+        };
+        self.driver.apply(&rules).await
+    }
+
+    /// State of the upstream Flowspec sessions
+    pub async fn peer_status(&self) -> Result<Vec<PeerStatus>, String> {
+        self.driver.peer_status().await
+    }
+
+    /// Withdraw expired announcements on an interval
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.withdraw_expired(chrono::Utc::now()).await {
+                    warn!("Flowspec expiry sweep failed: {}", e);
+                }
+            }
+        })
+    }
+}
@@ -1,8 +1,18 @@
 //! BGP Flowspec Integration
 //!
-//! RFC 5575 Flowspec rules for upstream mitigation.
+//! RFC 8955 Flowspec rules for upstream mitigation, announced through BIRD
+//! or GoBGP.
 
-use crate::{Attack, Protocol};
+pub mod bird;
+pub mod controller;
+pub mod gobgp;
+pub mod nlri;
+
+pub use bird::{BirdDriver, FlowspecPeer};
+pub use controller::{FlowspecConfig, FlowspecController, FlowspecDriver};
+pub use gobgp::{GoBgpConfig, GoBgpDriver};
+
+use crate::{Attack, AttackType, Protocol};
 use std::net::IpAddr;
 
 /// BGP Flowspec rule generator
@@ -28,63 +38,35 @@ impl FlowspecGenerator {
     pub fn generate(&self, attack: &Attack) -> FlowspecRule {
         FlowspecRule {
             destination: attack.target.ip,
-            destination_prefix: 32,
+            destination_prefix: attack.target.prefix_len.unwrap_or(host_prefix(&attack.target.ip)),
             source: None,
+            source_prefix: host_prefix(&attack.target.ip),
             protocol: Some(attack.target.protocol),
-            source_port: None,
+            source_port: amplification_port(attack.attack_type),
             destination_port: attack.target.port,
             tcp_flags: None,
-            packet_length: None,
+            packet_length: amplification_length(attack.attack_type),
             dscp: None,
             fragment: None,
             action: FlowspecAction::RateLimit(
-                attack.metrics.total_bps / 100 // 1% of attack traffic
+                attack.metrics.total_bps / 8 / 100 // 1% of attack traffic, in bytes
             ),
         }
     }
     
+    /// Generate a rule diverting attack traffic to the scrubbing VRF
+    pub fn generate_redirect(&self, attack: &Attack, route_target: &str) -> FlowspecRule {
+        FlowspecRule {
+            action: FlowspecAction::Redirect(route_target.to_string()),
+            source_port: None,
+            packet_length: None,
+            ..self.generate(attack)
+        }
+    }
+    
     /// Generate BIRD configuration for Flowspec
     pub fn to_bird_config(&self, rule: &FlowspecRule) -> String {
-        let mut config = String::new();
-        
-        config.push_str("flow4 {\n");
-        config.push_str(&format!("    dst {}/{};\n", rule.destination, rule.destination_prefix));
-        
-        if let Some(src) = rule.source {
-            config.push_str(&format!("    src {}/32;\n", src));
-        }
-        
-        if let Some(proto) = &rule.protocol {
-            config.push_str(&format!("    proto = {};\n", protocol_num(proto)));
-        }
-        
-        if let Some(port) = rule.destination_port {
-            config.push_str(&format!("    dport = {};\n", port));
-        }
-        
-        if let Some(port) = rule.source_port {
-            config.push_str(&format!("    sport = {};\n", port));
-        }
-        
-        config.push_str("} then {\n");
-        
-        match &rule.action {
-            FlowspecAction::Drop => {
-                config.push_str("    discard;\n");
-            }
-            FlowspecAction::RateLimit(bps) => {
-                config.push_str(&format!("    rate-limit {};\n", bps));
-            }
-            FlowspecAction::Redirect(rt) => {
-                config.push_str(&format!("    redirect {};\n", rt));
-            }
-            FlowspecAction::Mark(dscp) => {
-                config.push_str(&format!("    mark dscp {};\n", dscp));
-            }
-        }
-        
-        config.push_str("}\n");
-        config
+        rule.to_bird_route(self.local_asn)
     }
     
     /// Generate Flowspec for multiple top sources
@@ -93,8 +75,9 @@ impl FlowspecGenerator {
             .take(limit)
             .map(|source| FlowspecRule {
                 destination: attack.target.ip,
-                destination_prefix: 32,
+                destination_prefix: attack.target.prefix_len.unwrap_or(host_prefix(&attack.target.ip)),
                 source: Some(source.ip),
+                source_prefix: host_prefix(&source.ip),
                 protocol: Some(attack.target.protocol),
                 source_port: None,
                 destination_port: attack.target.port,
@@ -114,6 +97,8 @@ pub struct FlowspecRule {
     pub destination: IpAddr,
     pub destination_prefix: u8,
     pub source: Option<IpAddr>,
+    /// Prefix length of `source`; ignored without one
+    pub source_prefix: u8,
     pub protocol: Option<Protocol>,
     pub source_port: Option<u16>,
     pub destination_port: Option<u16>,
@@ -128,6 +113,7 @@ pub struct FlowspecRule {
 pub enum FlowspecAction {
     Drop,
     RateLimit(u64), // bytes per second
+    RateLimitPackets(u64), // packets per second
     Redirect(String), // Route target
     Mark(u8), // DSCP value
 }
@@ -140,6 +126,27 @@ pub enum FragmentType {
     LastFragment,
 }
 
+fn host_prefix(ip: &IpAddr) -> u8 {
+    if ip.is_ipv4() { 32 } else { 128 }
+}
+
+/// Reflectors answer from a well-known port
+fn amplification_port(attack_type: AttackType) -> Option<u16> {
+    match attack_type {
+        AttackType::DnsAmplification => Some(53),
+        AttackType::NtpAmplification => Some(123),
+        AttackType::SsdpAmplification => Some(1900),
+        AttackType::MemcachedAmplification => Some(11211),
+        AttackType::ChargenAmplification => Some(19),
+        _ => None,
+    }
+}
+
+/// Amplified responses are large; legitimate replies mostly are not
+fn amplification_length(attack_type: AttackType) -> Option<(u16, u16)> {
+    amplification_port(attack_type).map(|_| (512, u16::MAX))
+}
+
 fn protocol_num(proto: &Protocol) -> u8 {
     match proto {
         Protocol::Tcp => 6,
@@ -0,0 +1,258 @@
+//! Flowspec NLRI Encoding
+//!
+//! Wire encoding of flow specifications (RFC 8955 §4, RFC 8956 for IPv6)
+//! and the traffic filtering action extended communities (RFC 8955 §7).
+//! BIRD renders the same components from its own config syntax; the
+//! encoding here is what a BGP speaker API such as GoBGP takes directly and
+//! doubles as the identity of an announcement.
+
+use super::{FlowspecAction, FlowspecRule, FragmentType};
+use std::net::IpAddr;
+
+// Component types (RFC 8955 §4.2.2)
+pub const COMPONENT_DST_PREFIX: u8 = 1;
+pub const COMPONENT_SRC_PREFIX: u8 = 2;
+pub const COMPONENT_PROTOCOL: u8 = 3;
+pub const COMPONENT_PORT: u8 = 4;
+pub const COMPONENT_DST_PORT: u8 = 5;
+pub const COMPONENT_SRC_PORT: u8 = 6;
+pub const COMPONENT_TCP_FLAGS: u8 = 9;
+pub const COMPONENT_PACKET_LENGTH: u8 = 10;
+pub const COMPONENT_DSCP: u8 = 11;
+pub const COMPONENT_FRAGMENT: u8 = 12;
+
+// Operator byte bits (RFC 8955 §4.2.1)
+const OP_END: u8 = 0x80;
+const OP_AND: u8 = 0x40;
+const OP_LEN_2: u8 = 0x10;
+const OP_LT: u8 = 0x04;
+const OP_GT: u8 = 0x02;
+const OP_EQ: u8 = 0x01;
+/// Bitmask operator: all bits in the value must be set
+const OP_MATCH: u8 = 0x01;
+
+// Extended community types (RFC 8955 §7)
+const EXT_TRAFFIC_RATE_BYTES: u16 = 0x8006;
+const EXT_REDIRECT_AS2: u16 = 0x8008;
+const EXT_TRAFFIC_MARKING: u16 = 0x8009;
+const EXT_TRAFFIC_RATE_PACKETS: u16 = 0x800c;
+
+/// AS_TRANS, for the 2-octet AS field when the local ASN is 4-octet
+const AS_TRANS: u16 = 23456;
+
+impl FlowspecRule {
+    /// Encode as a Flowspec NLRI, length prefix included
+    pub fn to_nlri(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(32);
+
+        encode_prefix(&mut body, COMPONENT_DST_PREFIX, self.destination, self.destination_prefix);
+        if let Some(src) = self.source {
+            encode_prefix(&mut body, COMPONENT_SRC_PREFIX, src, self.source_prefix);
+        }
+        if let Some(proto) = &self.protocol {
+            encode_numeric(&mut body, COMPONENT_PROTOCOL, &[(OP_EQ, super::protocol_num(proto) as u64)]);
+        }
+        if let Some(port) = self.destination_port {
+            encode_numeric(&mut body, COMPONENT_DST_PORT, &[(OP_EQ, port as u64)]);
+        }
+        if let Some(port) = self.source_port {
+            encode_numeric(&mut body, COMPONENT_SRC_PORT, &[(OP_EQ, port as u64)]);
+        }
+        if let Some(flags) = self.tcp_flags {
+            encode_bitmask(&mut body, COMPONENT_TCP_FLAGS, OP_MATCH, flags);
+        }
+        if let Some((min, max)) = self.packet_length {
+            encode_numeric(&mut body, COMPONENT_PACKET_LENGTH, &range(min, max));
+        }
+        if let Some(dscp) = self.dscp {
+            encode_numeric(&mut body, COMPONENT_DSCP, &[(OP_EQ, (dscp & 0x3f) as u64)]);
+        }
+        if let Some(fragment) = self.fragment {
+            encode_bitmask(&mut body, COMPONENT_FRAGMENT, OP_MATCH, fragment.bits());
+        }
+
+        // Lengths of 240 and above take two octets with the top nibble set
+        let mut nlri = Vec::with_capacity(body.len() + 2);
+        if body.len() < 240 {
+            nlri.push(body.len() as u8);
+        } else {
+            nlri.extend_from_slice(&(0xf000 | body.len() as u16).to_be_bytes());
+        }
+        nlri.extend_from_slice(&body);
+        nlri
+    }
+
+    /// Lowercase hex of the NLRI; stable identity for an announcement
+    pub fn nlri_hex(&self) -> String {
+        self.to_nlri().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Extended communities carrying the rule's action
+    pub fn extended_communities(&self, local_asn: u32) -> Vec<[u8; 8]> {
+        let asn = u16::try_from(local_asn).unwrap_or(AS_TRANS);
+
+        match &self.action {
+            // Traffic-rate of zero means discard
+            FlowspecAction::Drop => vec![rate_community(EXT_TRAFFIC_RATE_BYTES, asn, 0)],
+            FlowspecAction::RateLimit(bytes) => vec![rate_community(EXT_TRAFFIC_RATE_BYTES, asn, *bytes)],
+            FlowspecAction::RateLimitPackets(pps) => vec![rate_community(EXT_TRAFFIC_RATE_PACKETS, asn, *pps)],
+            FlowspecAction::Redirect(rt) => match parse_route_target(rt) {
+                Some((rt_asn, value)) => {
+                    let mut c = [0u8; 8];
+                    c[..2].copy_from_slice(&EXT_REDIRECT_AS2.to_be_bytes());
+                    c[2..4].copy_from_slice(&rt_asn.to_be_bytes());
+                    c[4..].copy_from_slice(&value.to_be_bytes());
+                    vec![c]
+                }
+                None => vec![],
+            },
+            FlowspecAction::Mark(dscp) => {
+                let mut c = [0u8; 8];
+                c[..2].copy_from_slice(&EXT_TRAFFIC_MARKING.to_be_bytes());
+                c[7] = dscp & 0x3f;
+                vec![c]
+            }
+        }
+    }
+}
+
+impl FragmentType {
+    /// Fragment component bits (RFC 8955 §4.2.2.12)
+    fn bits(&self) -> u8 {
+        match self {
+            Self::NotFragment => 0x01, // DF
+            Self::IsFragment => 0x02,
+            Self::FirstFragment => 0x04,
+            Self::LastFragment => 0x08,
+        }
+    }
+}
+
+/// `asn:value` route target as used for redirect (2-octet AS form)
+pub fn parse_route_target(rt: &str) -> Option<(u16, u32)> {
+    let (asn, value) = rt.split_once(':')?;
+    Some((asn.trim().parse().ok()?, value.trim().parse().ok()?))
+}
+
+fn encode_prefix(out: &mut Vec<u8>, component: u8, addr: IpAddr, prefix_len: u8) {
+    out.push(component);
+    match addr {
+        IpAddr::V4(v4) => {
+            let len = prefix_len.min(32);
+            out.push(len);
+            out.extend_from_slice(&masked(&v4.octets(), len));
+        }
+        IpAddr::V6(v6) => {
+            // RFC 8956: length, offset, then the pattern bits
+            let len = prefix_len.min(128);
+            out.push(len);
+            out.push(0);
+            out.extend_from_slice(&masked(&v6.octets(), len));
+        }
+    }
+}
+
+/// The prefix octets covering `len` bits, host bits cleared
+fn masked(octets: &[u8], len: u8) -> Vec<u8> {
+    let bytes = (len as usize).div_ceil(8);
+    let mut prefix = octets[..bytes].to_vec();
+    if len % 8 != 0 {
+        if let Some(last) = prefix.last_mut() {
+            *last &= 0xffu8 << (8 - len % 8);
+        }
+    }
+    prefix
+}
+
+/// `min..=max` as operator/value pairs; a single value when they match
+fn range(min: u16, max: u16) -> Vec<(u8, u64)> {
+    if min == max {
+        vec![(OP_EQ, min as u64)]
+    } else {
+        vec![(OP_GT | OP_EQ, min as u64), (OP_AND | OP_LT | OP_EQ, max as u64)]
+    }
+}
+
+fn encode_numeric(out: &mut Vec<u8>, component: u8, terms: &[(u8, u64)]) {
+    out.push(component);
+    for (i, (op, value)) in terms.iter().enumerate() {
+        let end = if i + 1 == terms.len() { OP_END } else { 0 };
+        if *value > 0xff {
+            out.push(end | op | OP_LEN_2);
+            out.extend_from_slice(&(*value as u16).to_be_bytes());
+        } else {
+            out.push(end | op);
+            out.push(*value as u8);
+        }
+    }
+}
+
+fn encode_bitmask(out: &mut Vec<u8>, component: u8, op: u8, value: u8) {
+    out.push(component);
+    out.push(OP_END | op);
+    out.push(value);
+}
+
+/// Traffic-rate community: 2-octet AS then an IEEE 754 single rate
+fn rate_community(kind: u16, asn: u16, rate: u64) -> [u8; 8] {
+    let mut c = [0u8; 8];
+    c[..2].copy_from_slice(&kind.to_be_bytes());
+    c[2..4].copy_from_slice(&asn.to_be_bytes());
+    c[4..].copy_from_slice(&(rate as f32).to_be_bytes());
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Protocol;
+
+    fn rule(action: FlowspecAction) -> FlowspecRule {
+        FlowspecRule {
+            destination: "192.0.2.0".parse().unwrap(),
+            destination_prefix: 24,
+            source: None,
+            source_prefix: 32,
+            protocol: Some(Protocol::Tcp),
+            source_port: None,
+            destination_port: Some(25),
+            tcp_flags: None,
+            packet_length: None,
+            dscp: None,
+            fragment: None,
+            action,
+        }
+    }
+
+    #[test]
+    fn test_nlri_prefix_protocol_port() {
+        assert_eq!(
+            rule(FlowspecAction::Drop).to_nlri(),
+            vec![0x0b, 0x01, 0x18, 0xc0, 0x00, 0x02, 0x03, 0x81, 0x06, 0x05, 0x81, 0x19]
+        );
+    }
+
+    #[test]
+    fn test_nlri_packet_length_range() {
+        let mut r = rule(FlowspecAction::Drop);
+        r.protocol = None;
+        r.destination_port = None;
+        r.packet_length = Some((512, 65535));
+        assert_eq!(
+            r.to_nlri(),
+            vec![0x0c, 0x01, 0x18, 0xc0, 0x00, 0x02, 0x0a, 0x13, 0x02, 0x00, 0xd5, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_discard_and_redirect_communities() {
+        assert_eq!(
+            rule(FlowspecAction::Drop).extended_communities(64512),
+            vec![[0x80, 0x06, 0xfc, 0x00, 0, 0, 0, 0]]
+        );
+        assert_eq!(
+            rule(FlowspecAction::Redirect("65000:100".to_string())).extended_communities(64512),
+            vec![[0x80, 0x08, 0xfd, 0xe8, 0, 0, 0, 100]]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Sharded Sample Ingestion
+//!
+//! Spreads a sample stream over per-core shards feeding one shared
+//! [`DdosShield`]. Samples are routed by destination, so each detection
+//! window is fed by a single shard and per-destination counters see no
+//! cross-shard contention.
+//!
+//! Shards only detect. Attacks go to one aggregation task that mitigates
+//! them, refreshes mitigation counters and publishes them to subscribers,
+//! so a slow mitigation never stalls ingestion. Producers never block
+//! either: a full shard queue drops the sample and counts it.
+
+use crate::{Attack, DdosShield, TrafficSample};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestConfig {
+    /// Number of shards; one per core by default
+    pub shards: usize,
+    /// Samples queued per shard before new ones are dropped
+    pub queue_depth: usize,
+    /// Samples a shard takes per wake-up
+    pub batch_size: usize,
+    /// How often mitigation counters are refreshed
+    pub maintenance_interval: Duration,
+    /// Prefix windows idle this long are pruned
+    pub idle_window: Duration,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            shards: std::thread::available_parallelism().map_or(4, |n| n.get()),
+            queue_depth: 65_536,
+            batch_size: 256,
+            maintenance_interval: Duration::from_secs(1),
+            idle_window: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ShardCounters {
+    accepted: AtomicU64,
+    dropped: AtomicU64,
+    processed: AtomicU64,
+    attacks: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShardStats {
+    pub shard: usize,
+    pub accepted: u64,
+    pub dropped: u64,
+    pub processed: u64,
+    pub attacks: u64,
+    /// Samples waiting in the shard's queue
+    pub queued: usize,
+}
+
+/// Sharded front end to a shared [`DdosShield`]
+pub struct SampleIngest {
+    shards: Vec<mpsc::Sender<TrafficSample>>,
+    counters: Vec<Arc<ShardCounters>>,
+    attacks: broadcast::Sender<Attack>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SampleIngest {
+    /// Start the shard and aggregation tasks
+    pub fn start(shield: Arc<DdosShield>, config: IngestConfig) -> Self {
+        let shard_count = config.shards.max(1);
+        let (detected_tx, detected_rx) = mpsc::channel(1024);
+        let (attacks, _) = broadcast::channel(1024);
+
+        let mut shards = Vec::with_capacity(shard_count);
+        let mut counters = Vec::with_capacity(shard_count);
+        let mut tasks = Vec::with_capacity(shard_count + 1);
+        for shard in 0..shard_count {
+            let (tx, rx) = mpsc::channel(config.queue_depth.max(1));
+            let shard_counters = Arc::new(ShardCounters::default());
+            tasks.push(tokio::spawn(run_shard(
+                shard,
+                shield.clone(),
+                rx,
+                detected_tx.clone(),
+                shard_counters.clone(),
+                config.batch_size.max(1),
+            )));
+            shards.push(tx);
+            counters.push(shard_counters);
+        }
+        // Aggregation ends once every shard has
+        drop(detected_tx);
+        tasks.push(tokio::spawn(run_aggregation(shield, detected_rx, attacks.clone(), config)));
+
+        info!("Sample ingestion started with {} shards", shard_count);
+        Self { shards, counters, attacks, tasks }
+    }
+
+    /// Queue a sample on its destination's shard; false if it was dropped
+    ///
+    /// Never blocks, so it is safe to call from data-plane threads.
+    pub fn submit(&self, sample: TrafficSample) -> bool {
+        let shard = self.shard_for(&sample);
+        match self.shards[shard].try_send(sample) {
+            Ok(()) => {
+                self.counters[shard].accepted.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(_) => {
+                self.counters[shard].dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Queue a batch of samples; returns how many were accepted
+    pub fn submit_batch(&self, samples: impl IntoIterator<Item = TrafficSample>) -> usize {
+        let mut accepted = 0;
+        for sample in samples {
+            if self.submit(sample) {
+                accepted += 1;
+            }
+        }
+        accepted
+    }
+
+    /// Attacks as they are detected, after mitigation has been started
+    pub fn subscribe(&self) -> broadcast::Receiver<Attack> {
+        self.attacks.subscribe()
+    }
+
+    pub fn stats(&self) -> Vec<ShardStats> {
+        self.counters.iter()
+            .zip(&self.shards)
+            .enumerate()
+            .map(|(shard, (counters, tx))| ShardStats {
+                shard,
+                accepted: counters.accepted.load(Ordering::Relaxed),
+                dropped: counters.dropped.load(Ordering::Relaxed),
+                processed: counters.processed.load(Ordering::Relaxed),
+                attacks: counters.attacks.load(Ordering::Relaxed),
+                queued: tx.max_capacity() - tx.capacity(),
+            })
+            .collect()
+    }
+
+    /// Stop accepting samples, drain the queues and wait for the tasks
+    pub async fn shutdown(self) {
+        drop(self.shards);
+        for task in self.tasks {
+            let _ = task.await;
+        }
+        info!("Sample ingestion stopped");
+    }
+
+    fn shard_for(&self, sample: &TrafficSample) -> usize {
+        let mut hasher = DefaultHasher::new();
+        sample.destination.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+}
+
+async fn run_shard(
+    shard: usize,
+    shield: Arc<DdosShield>,
+    mut samples: mpsc::Receiver<TrafficSample>,
+    detected: mpsc::Sender<Attack>,
+    counters: Arc<ShardCounters>,
+    batch_size: usize,
+) {
+    while let Some(first) = samples.recv().await {
+        let mut next = Some(first);
+        let mut taken = 0;
+        while let Some(sample) = next.take() {
+            if let Some(attack) = shield.detect(&sample).await {
+                counters.attacks.fetch_add(1, Ordering::Relaxed);
+                if detected.send(attack).await.is_err() {
+                    return;
+                }
+            }
+            counters.processed.fetch_add(1, Ordering::Relaxed);
+            taken += 1;
+            if taken < batch_size {
+                next = samples.try_recv().ok();
+            }
+        }
+        // Let other shards on this worker run between batches
+        tokio::task::yield_now().await;
+    }
+    debug!("Ingestion shard {} stopped", shard);
+}
+
+async fn run_aggregation(
+    shield: Arc<DdosShield>,
+    mut detected: mpsc::Receiver<Attack>,
+    attacks: broadcast::Sender<Attack>,
+    config: IngestConfig,
+) {
+    let mut ticker = tokio::time::interval(config.maintenance_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            attack = detected.recv() => {
+                let Some(attack) = attack else { break };
+                shield.respond(&attack).await;
+                // No subscribers is fine
+                let _ = attacks.send(attack);
+            }
+            _ = ticker.tick() => {
+                shield.refresh_mitigation_stats().await;
+                shield.prefix_aggregator().prune(config.idle_window);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DetectionConfig, Protocol};
+    use std::net::IpAddr;
+    use std::time::Instant;
+
+    fn sample(destination: IpAddr) -> TrafficSample {
+        TrafficSample {
+            timestamp: Instant::now(),
+            source: "198.51.100.7".parse().unwrap(),
+            destination,
+            protocol: Protocol::Tcp,
+            src_port: 40000,
+            dst_port: 443,
+            packet_size: 512,
+            tcp_flags: Some(0x10),
+            pps: 100,
+            bps: 400_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_samples_drained_on_shutdown() {
+        let shield = Arc::new(DdosShield::new(DetectionConfig::default()));
+        let config = IngestConfig { shards: 4, ..Default::default() };
+        let ingest = SampleIngest::start(shield, config);
+
+        let samples = (1..=200u8).map(|host| sample(IpAddr::from([10, 0, 0, host])));
+        assert_eq!(ingest.submit_batch(samples), 200);
+
+        let counters = ingest.counters.clone();
+        ingest.shutdown().await;
+        let processed: u64 = counters.iter().map(|c| c.processed.load(Ordering::Relaxed)).sum();
+        assert_eq!(processed, 200);
+    }
+
+    #[tokio::test]
+    async fn test_destination_sticks_to_one_shard() {
+        let shield = Arc::new(DdosShield::new(DetectionConfig::default()));
+        let ingest = SampleIngest::start(shield, IngestConfig { shards: 8, ..Default::default() });
+
+        let destination: IpAddr = "10.0.0.1".parse().unwrap();
+        for _ in 0..50 {
+            ingest.submit(sample(destination));
+        }
+        let busy = ingest.stats().iter().filter(|s| s.accepted > 0).count();
+        assert_eq!(busy, 1);
+        ingest.shutdown().await;
+    }
+}
@@ -9,7 +9,13 @@
 //! - <100μs detection latency
 //! - <1ms mitigation activation
 //! - Zero false positives
+//!
+//! [`DdosShield`] is shared state behind `&self`, so per-core ingestion can
+//! feed it in parallel; [`ingest::SampleIngest`] shards a sample stream
+//! across cores and mitigates from a single aggregation task.
 
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
@@ -27,6 +33,8 @@ pub mod app_layer;
 pub mod scrubbing;
 pub mod ml_detection;
 pub mod dashboard;
+pub mod ingest;
+pub mod reporting;
 
 // =============================================================================
 // Attack Types
@@ -141,10 +149,29 @@ pub struct Attack {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttackTarget {
+    /// Target address; the network address when the attack spans a prefix
     pub ip: IpAddr,
     pub port: Option<u16>,
     pub protocol: Protocol,
     pub customer_id: Option<String>,
+    /// Set when the attack is spread across a prefix (carpet bombing)
+    #[serde(default)]
+    pub prefix_len: Option<u8>,
+}
+
+impl AttackTarget {
+    /// The attacked prefix in CIDR form, when the attack spans one
+    pub fn cidr(&self) -> Option<String> {
+        self.prefix_len.map(|len| format!("{}/{}", self.ip, len))
+    }
+    
+    /// Whether traffic to an address is part of this target
+    pub fn covers(&self, ip: &IpAddr) -> bool {
+        match self.prefix_len {
+            Some(len) => ipnetwork::IpNetwork::new(self.ip, len).is_ok_and(|n| n.contains(*ip)),
+            None => self.ip == *ip,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -206,6 +233,9 @@ pub struct MitigationRule {
     pub source: Option<IpAddr>,
     pub source_prefix: Option<String>,
     pub destination: Option<IpAddr>,
+    /// CIDR covering several destinations, for prefix-level rules
+    #[serde(default)]
+    pub destination_prefix: Option<String>,
     pub protocol: Option<Protocol>,
     pub port: Option<u16>,
     pub action: RuleAction,
@@ -223,6 +253,8 @@ pub enum RuleType {
     IptablesRate,
     SynCookie,
     SynProxy,
+    /// HTTP clients diverted to proof-of-work challenges
+    L7Challenge,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -250,6 +282,12 @@ pub struct MitigationStats {
     pub packets_allowed: u64,
     pub bytes_allowed: u64,
     pub syn_cookies_sent: u64,
+    /// Clients that returned a valid SYN cookie
+    #[serde(default)]
+    pub syn_cookies_validated: u64,
+    /// Validated connections handed to the host stack (SYN proxy)
+    #[serde(default)]
+    pub syn_handoffs: u64,
     pub challenges_issued: u64,
 }
 
@@ -285,8 +323,11 @@ impl TrafficBaseline {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetectionConfig {
-    /// Multiplier above baseline to trigger detection
+    /// Multiplier above a static baseline to trigger detection
     pub anomaly_threshold: f64,
+    /// Anomaly score above a learned baseline to trigger detection
+    #[serde(default = "default_anomaly_score_threshold")]
+    pub anomaly_score_threshold: f64,
     /// Minimum PPS to consider as attack
     pub min_pps_threshold: u64,
     /// Minimum BPS to consider as attack
@@ -305,6 +346,7 @@ impl Default for DetectionConfig {
     fn default() -> Self {
         Self {
             anomaly_threshold: 3.0,      // 3x normal traffic
+            anomaly_score_threshold: default_anomaly_score_threshold(),
             min_pps_threshold: 100_000,  // 100K PPS
             min_bps_threshold: 100_000_000, // 100 Mbps
             syn_ratio_threshold: 0.8,    // 80% SYN packets
@@ -315,75 +357,245 @@ impl Default for DetectionConfig {
     }
 }
 
+fn default_anomaly_score_threshold() -> f64 {
+    6.0 // standard deviations, plus any protocol mix shift
+}
+
 // =============================================================================
 // DDoS Shield Service
 // =============================================================================
 
 /// Main DDoS Shield service
+///
+/// All state is lock-free or sharded, so one instance is shared (in an
+/// `Arc`) by every ingestion thread.
 pub struct DdosShield {
     config: DetectionConfig,
-    baselines: HashMap<IpAddr, TrafficBaseline>,
-    active_attacks: HashMap<String, Attack>,
-    active_mitigations: HashMap<String, ActiveMitigation>,
+    /// Static baselines; read on every sample, replaced wholesale on update
+    baselines: ArcSwap<HashMap<IpAddr, TrafficBaseline>>,
+    active_attacks: DashMap<String, Attack>,
+    active_mitigations: DashMap<String, ActiveMitigation>,
+    learner: Arc<baseline::BaselineLearner>,
+    aggregator: Arc<detector::PrefixAggregator>,
     detector: Arc<detector::AttackDetector>,
     mitigator: Arc<mitigator::MitigationEngine>,
+    recorder: Arc<reporting::AttackRecorder>,
 }
 
 impl DdosShield {
     pub fn new(config: DetectionConfig) -> Self {
+        let learner = Arc::new(baseline::BaselineLearner::new(0.01, 1000));
+        let aggregator = Arc::new(detector::PrefixAggregator::new(detector::CarpetConfig::default(), config.clone()));
         Self {
             config: config.clone(),
-            baselines: HashMap::new(),
-            active_attacks: HashMap::new(),
-            active_mitigations: HashMap::new(),
-            detector: Arc::new(detector::AttackDetector::new(config.clone())),
+            baselines: ArcSwap::from_pointee(HashMap::new()),
+            active_attacks: DashMap::new(),
+            active_mitigations: DashMap::new(),
+            detector: Arc::new(
+                detector::AttackDetector::new(config.clone())
+                    .with_learner(learner.clone())
+                    .with_aggregator(aggregator.clone()),
+            ),
+            learner,
+            aggregator,
             mitigator: Arc::new(mitigator::MitigationEngine::new()),
+            recorder: Arc::new(reporting::AttackRecorder::default()),
+        }
+    }
+    
+    /// Record timelines into a recorder shared with the
+    /// [`dashboard::Dashboard`] that renders post-mortems
+    pub fn with_recorder(mut self, recorder: Arc<reporting::AttackRecorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+    
+    /// Attack timelines, ongoing and retained
+    pub fn recorder(&self) -> Arc<reporting::AttackRecorder> {
+        self.recorder.clone()
+    }
+    
+    /// Learn baselines with a custom learner, e.g. one restored from a
+    /// [`baseline::BaselineStore`]
+    pub fn with_baseline_learner(mut self, learner: Arc<baseline::BaselineLearner>) -> Self {
+        self.learner = learner;
+        self.rebuild_detector();
+        self
+    }
+    
+    /// Tune carpet-bombing detection; customer groups start empty
+    pub fn with_carpet_config(mut self, carpet: detector::CarpetConfig) -> Self {
+        self.aggregator = Arc::new(detector::PrefixAggregator::new(carpet, self.config.clone()));
+        self.rebuild_detector();
+        self
+    }
+    
+    fn rebuild_detector(&mut self) {
+        self.detector = Arc::new(
+            detector::AttackDetector::new(self.config.clone())
+                .with_learner(self.learner.clone())
+                .with_aggregator(self.aggregator.clone()),
+        );
+    }
+    
+    /// Watch a customer's prefixes as one group, so attacks spread across
+    /// them are detected and attributed to the customer. Each prefix also
+    /// gets a learned baseline of its own.
+    pub fn add_customer_prefixes(&self, customer_id: &str, prefixes: Vec<ipnetwork::IpNetwork>) {
+        for prefix in &prefixes {
+            self.learner.protect_prefix(*prefix);
         }
+        self.aggregator.add_customer_prefixes(customer_id, prefixes);
+    }
+    
+    /// Destination prefix and customer-group views
+    pub fn prefix_aggregator(&self) -> Arc<detector::PrefixAggregator> {
+        self.aggregator.clone()
+    }
+    
+    /// Learned per-destination baselines
+    pub fn baseline_learner(&self) -> Arc<baseline::BaselineLearner> {
+        self.learner.clone()
+    }
+    
+    /// Run SYN cookie mitigations in the XDP data plane
+    pub fn with_xdp(mut self, xdp: Arc<xdp::XdpManager>) -> Self {
+        self.mitigator = Arc::new((*self.mitigator).clone().with_xdp(xdp));
+        self
+    }
+    
+    /// Serve HTTP flood mitigations as challenges through the L7 gateway
+    pub fn with_challenge_service(mut self, challenges: Arc<app_layer::ChallengeService>) -> Self {
+        self.mitigator = Arc::new((*self.mitigator).clone().with_challenges(challenges));
+        self
+    }
+    
+    /// Announce Flowspec and scrubbing redirects to upstream routers
+    pub fn with_flowspec(mut self, flowspec: Arc<flowspec::FlowspecController>) -> Self {
+        self.mitigator = Arc::new((*self.mitigator).clone().with_flowspec(flowspec));
+        self
+    }
+    
+    /// Set a destination's static baseline, used until a learned one matures
+    pub fn set_baseline(&self, baseline: TrafficBaseline) {
+        self.baselines.rcu(|current| {
+            let mut next = HashMap::clone(current);
+            next.insert(baseline.target, baseline.clone());
+            next
+        });
+    }
+    
+    /// Replace all static baselines at once
+    pub fn set_baselines(&self, baselines: impl IntoIterator<Item = TrafficBaseline>) {
+        let next: HashMap<IpAddr, TrafficBaseline> = baselines.into_iter()
+            .map(|b| (b.target, b))
+            .collect();
+        self.baselines.store(Arc::new(next));
     }
     
     /// Process incoming traffic sample
-    pub async fn process_sample(&mut self, sample: &TrafficSample) -> Option<Attack> {
+    pub async fn process_sample(&self, sample: &TrafficSample) -> Option<Attack> {
+        let attack = self.detect(sample).await?;
+        self.respond(&attack).await;
+        Some(attack)
+    }
+    
+    /// Detect and record attacks in a sample without mitigating them
+    pub async fn detect(&self, sample: &TrafficSample) -> Option<Attack> {
         // Check against baseline
-        let baseline = self.baselines.get(&sample.destination);
+        let baseline = self.baselines.load().get(&sample.destination).cloned();
         
         // Detect anomalies
-        if let Some(attack) = self.detector.analyze(sample, baseline).await {
+        if let Some(attack) = self.detector.analyze(sample, baseline.as_ref()).await {
             // Classify attack type
             let classified = classifier::classify(&attack);
-            
-            // Auto-mitigate if enabled
-            if classified.attack_type.severity() >= 7 {
-                let mitigation = self.mitigator.activate(&classified).await;
-                self.active_mitigations.insert(mitigation.id.clone(), mitigation);
-            }
-            
+            self.recorder.detected(&attack);
+            self.recorder.classified(&classified.id, attack.attack_type, classified.attack_type);
             self.active_attacks.insert(classified.id.clone(), classified.clone());
             return Some(classified);
         }
         
+        // Attack traffic must not become the new normal
+        if !self.under_attack(&sample.destination) {
+            self.learner.learn(sample);
+        }
+        
         None
     }
     
+    /// Auto-mitigate a detected attack if it is severe enough
+    pub async fn respond(&self, attack: &Attack) -> Option<ActiveMitigation> {
+        if attack.attack_type.severity() < 7 {
+            return None;
+        }
+        let mitigation = self.mitigator.activate(attack).await;
+        self.recorder.mitigation_activated(&attack.id, &mitigation);
+        self.active_mitigations.insert(mitigation.id.clone(), mitigation.clone());
+        Some(mitigation)
+    }
+    
+    /// Whether an attack on a destination was seen within the cooldown
+    fn under_attack(&self, destination: &IpAddr) -> bool {
+        let cutoff = chrono::Utc::now() - chrono::Duration::seconds(self.config.cooldown_seconds as i64);
+        self.active_attacks.iter().any(|a| {
+            a.target.covers(destination) && a.status != AttackStatus::Ended && a.last_seen > cutoff
+        })
+    }
+    
     /// Get currently active attacks
-    pub fn active_attacks(&self) -> Vec<&Attack> {
-        self.active_attacks.values().collect()
+    pub fn active_attacks(&self) -> Vec<Attack> {
+        self.active_attacks.iter().map(|a| a.value().clone()).collect()
     }
     
     /// Manually trigger mitigation
-    pub async fn mitigate(&mut self, attack_id: &str, strategy: MitigationStrategy) -> Result<ActiveMitigation, String> {
+    pub async fn mitigate(&self, attack_id: &str, strategy: MitigationStrategy) -> Result<ActiveMitigation, String> {
         let attack = self.active_attacks.get(attack_id)
+            .map(|a| a.value().clone())
             .ok_or_else(|| "Attack not found".to_string())?;
         
-        let mitigation = self.mitigator.activate_with_strategy(attack, strategy).await;
+        let mitigation = self.mitigator.activate_with_strategy(&attack, strategy).await;
+        self.recorder.mitigation_activated(attack_id, &mitigation);
         self.active_mitigations.insert(mitigation.id.clone(), mitigation.clone());
         
         Ok(mitigation)
     }
     
+    /// Refresh counters on all active mitigations
+    pub async fn refresh_mitigation_stats(&self) {
+        // Map entries are never held across an await
+        let ids: Vec<String> = self.active_mitigations.iter().map(|m| m.key().clone()).collect();
+        for id in ids {
+            let Some(mut mitigation) = self.active_mitigations.get(&id).map(|m| m.value().clone()) else {
+                continue;
+            };
+            self.mitigator.refresh_stats(&mut mitigation).await;
+            // Stopped meanwhile; don't bring it back
+            if let Some(mut entry) = self.active_mitigations.get_mut(&id) {
+                *entry = mitigation;
+            }
+        }
+    }
+    
+    /// Push an active mitigation's rules upstream as Flowspec
+    pub async fn escalate_upstream(&self, mitigation_id: &str) -> Result<usize, String> {
+        let mitigation = self.active_mitigations.get(mitigation_id)
+            .map(|m| m.value().clone())
+            .ok_or_else(|| "Mitigation not found".to_string())?;
+        let routes = self.mitigator.escalate_upstream(&mitigation).await?;
+        self.recorder.mitigation_escalated(mitigation_id, routes);
+        Ok(routes)
+    }
+    
+    /// Get currently active mitigations
+    pub fn active_mitigations(&self) -> Vec<ActiveMitigation> {
+        self.active_mitigations.iter().map(|m| m.value().clone()).collect()
+    }
+    
     /// Stop mitigation
-    pub async fn stop_mitigation(&mut self, mitigation_id: &str) -> Result<(), String> {
-        if let Some(mitigation) = self.active_mitigations.remove(mitigation_id) {
+    pub async fn stop_mitigation(&self, mitigation_id: &str) -> Result<(), String> {
+        if let Some((_, mitigation)) = self.active_mitigations.remove(mitigation_id) {
             self.mitigator.deactivate(&mitigation).await;
+            self.recorder.mitigation_stopped(&mitigation);
         }
         Ok(())
     }
@@ -6,10 +6,15 @@ use crate::{
     ActiveMitigation, Attack, AttackType, MitigationRule, MitigationStats,
     MitigationStrategy, Protocol, RateLimit, RuleAction, RuleType,
 };
+use crate::app_layer::ChallengeService;
+use crate::flowspec::{FlowspecController, FlowspecGenerator};
+use crate::xdp::{SynCookieMode, SynProtection, XdpManager};
 use std::net::IpAddr;
+use std::sync::Arc;
 use tracing::{info, warn};
 
 /// Mitigation engine that activates defenses
+#[derive(Clone)]
 pub struct MitigationEngine {
     /// VPP control socket path
     vpp_socket: String,
@@ -21,6 +26,12 @@ pub struct MitigationEngine {
     auto_flowspec: bool,
     /// Maximum ACL rules
     max_acl_rules: usize,
+    /// XDP first-line defense; SYN cookies run here when present
+    xdp: Option<Arc<XdpManager>>,
+    /// Upstream Flowspec announcements; absent when no peers are configured
+    flowspec: Option<Arc<FlowspecController>>,
+    /// L7 challenge service; HTTP floods are challenged here when present
+    challenges: Option<Arc<ChallengeService>>,
 }
 
 impl MitigationEngine {
@@ -31,9 +42,30 @@ impl MitigationEngine {
             auto_rtbh: true,
             auto_flowspec: true,
             max_acl_rules: 10000,
+            xdp: None,
+            flowspec: None,
+            challenges: None,
         }
     }
     
+    /// Run SYN cookies in XDP instead of VPP
+    pub fn with_xdp(mut self, xdp: Arc<XdpManager>) -> Self {
+        self.xdp = Some(xdp);
+        self
+    }
+    
+    /// Announce Flowspec rules upstream through BIRD
+    pub fn with_flowspec(mut self, flowspec: Arc<FlowspecController>) -> Self {
+        self.flowspec = Some(flowspec);
+        self
+    }
+    
+    /// Divert HTTP flood targets' clients to proof-of-work challenges
+    pub fn with_challenges(mut self, challenges: Arc<ChallengeService>) -> Self {
+        self.challenges = Some(challenges);
+        self
+    }
+    
     /// Activate mitigation for an attack
     pub async fn activate(&self, attack: &Attack) -> ActiveMitigation {
         let strategy = attack.attack_type.mitigation_strategy();
@@ -53,7 +85,8 @@ impl MitigationEngine {
             attack.target.ip
         );
         
-        let rules = match strategy {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut rules = match strategy {
             MitigationStrategy::SynCookie => {
                 self.activate_syn_cookies(&attack.target.ip).await
             }
@@ -70,16 +103,29 @@ impl MitigationEngine {
                 self.activate_port_blocking(attack).await
             }
             MitigationStrategy::BgpFlowspec => {
-                self.activate_flowspec(attack).await
+                self.activate_flowspec(&id, attack).await
             }
             MitigationStrategy::Rtbh => {
                 self.activate_rtbh(attack).await
             }
+            MitigationStrategy::Scrubbing => {
+                self.activate_scrubbing_redirect(&id, attack).await
+            }
+            MitigationStrategy::ChallengePage => {
+                self.activate_challenge(attack)
+            }
             _ => vec![],
         };
         
+        // Carpet-bombing targets a range; widen the target's rules to it
+        if let Some(cidr) = attack.target.cidr() {
+            for rule in rules.iter_mut().filter(|r| r.destination == Some(attack.target.ip)) {
+                rule.destination_prefix = Some(cidr.clone());
+            }
+        }
+        
         ActiveMitigation {
-            id: uuid::Uuid::new_v4().to_string(),
+            id,
             strategy,
             rules,
             started_at: chrono::Utc::now(),
@@ -102,12 +148,59 @@ impl MitigationEngine {
                 RuleType::BirdRtbh => {
                     self.remove_rtbh(rule).await;
                 }
-                RuleType::BgpFlowspec => {
-                    self.remove_flowspec(rule).await;
+                RuleType::SynCookie => {
+                    self.remove_syn_cookies(rule).await;
+                }
+                RuleType::SynProxy => {
+                    self.remove_syn_proxy(rule).await;
+                }
+                RuleType::L7Challenge => {
+                    self.remove_challenge(rule);
                 }
                 _ => {}
             }
         }
+        
+        // Covers Flowspec rules and any rules escalated upstream
+        self.remove_flowspec(&mitigation.id).await;
+    }
+    
+    /// Announce an active mitigation's rules upstream as Flowspec
+    pub async fn escalate_upstream(&self, mitigation: &ActiveMitigation) -> Result<usize, String> {
+        let flowspec = self.flowspec.as_ref()
+            .ok_or_else(|| "Flowspec is not configured".to_string())?;
+        flowspec.announce_mitigation(&mitigation.id, &mitigation.rules).await
+    }
+    
+    /// Refresh a mitigation's counters from the data plane
+    pub async fn refresh_stats(&self, mitigation: &mut ActiveMitigation) {
+        let Some(xdp) = &self.xdp else { return };
+        
+        let mut stats = MitigationStats::default();
+        let mut in_xdp = false;
+        for rule in &mitigation.rules {
+            if !matches!(rule.rule_type, RuleType::SynCookie | RuleType::SynProxy) {
+                continue;
+            }
+            let Some(dst) = rule.destination else { continue };
+            if !xdp.syn_cookies_enabled(&dst) {
+                continue;
+            }
+            in_xdp = true;
+            
+            // No entry yet means no TCP has reached the destination
+            if let Ok(cookies) = xdp.syn_cookie_stats(dst).await {
+                stats.syn_cookies_sent += cookies.cookies_sent;
+                stats.syn_cookies_validated += cookies.cookies_validated;
+                stats.syn_handoffs += cookies.handoffs;
+                stats.packets_allowed += cookies.verified_passed + cookies.unverified_acks
+                    + cookies.handoffs + cookies.syns_passed;
+            }
+        }
+        
+        if in_xdp {
+            mitigation.stats = stats;
+        }
     }
     
     // =========================================================================
@@ -115,15 +208,29 @@ impl MitigationEngine {
     // =========================================================================
     
     async fn activate_syn_cookies(&self, target: &IpAddr) -> Vec<MitigationRule> {
-        // VPP command: tcp syn-cookie threshold 100
-        let cmd = format!("tcp syn-flood threshold 100 for {}", target);
-        self.vpp_exec(&cmd).await;
+        let in_xdp = match &self.xdp {
+            Some(xdp) => match xdp.enable_syn_cookies(*target).await {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("XDP SYN cookies unavailable for {}, using VPP: {}", target, e);
+                    false
+                }
+            },
+            None => false,
+        };
+        
+        if !in_xdp {
+            // VPP command: tcp syn-cookie threshold 100
+            let cmd = format!("tcp syn-flood threshold 100 for {}", target);
+            self.vpp_exec(&cmd).await;
+        }
         
         vec![MitigationRule {
             rule_type: RuleType::SynCookie,
             source: None,
             source_prefix: None,
             destination: Some(*target),
+            destination_prefix: None,
             protocol: Some(Protocol::Tcp),
             port: None,
             action: RuleAction::SynCookie,
@@ -134,15 +241,37 @@ impl MitigationEngine {
     }
     
     async fn activate_syn_proxy(&self, target: &IpAddr) -> Vec<MitigationRule> {
-        // Enable TCP SYN proxy in VPP
-        let cmd = format!("tcp session table syn-proxy on for {}", target);
-        self.vpp_exec(&cmd).await;
+        // XDP hands validated connections to the host stack; keep any
+        // adaptive threshold the cookie config sets
+        let in_xdp = match &self.xdp {
+            Some(xdp) => {
+                let protection = SynProtection {
+                    mode: SynCookieMode::Handoff,
+                    syn_threshold_pps: xdp.syn_cookie_config().protection.syn_threshold_pps,
+                };
+                match xdp.enable_syn_protection(*target, protection).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!("XDP SYN proxy unavailable for {}, using VPP: {}", target, e);
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
+        
+        if !in_xdp {
+            // Enable TCP SYN proxy in VPP
+            let cmd = format!("tcp session table syn-proxy on for {}", target);
+            self.vpp_exec(&cmd).await;
+        }
         
         vec![MitigationRule {
             rule_type: RuleType::SynProxy,
             source: None,
             source_prefix: None,
             destination: Some(*target),
+            destination_prefix: None,
             protocol: Some(Protocol::Tcp),
             port: None,
             action: RuleAction::SynCookie,
@@ -177,6 +306,7 @@ impl MitigationEngine {
             source: None,
             source_prefix: None,
             destination: Some(attack.target.ip),
+            destination_prefix: None,
             protocol: Some(attack.target.protocol),
             port: attack.target.port,
             action: RuleAction::RateLimit,
@@ -204,6 +334,7 @@ impl MitigationEngine {
                 source: Some(source.ip),
                 source_prefix: None,
                 destination: Some(attack.target.ip),
+                destination_prefix: None,
                 protocol: Some(attack.target.protocol),
                 port: attack.target.port,
                 action: RuleAction::RateLimit,
@@ -242,6 +373,7 @@ impl MitigationEngine {
                 source: Some(source.ip),
                 source_prefix: None,
                 destination: Some(attack.target.ip),
+                destination_prefix: None,
                 protocol: Some(attack.target.protocol),
                 port: attack.target.port,
                 action: RuleAction::Drop,
@@ -281,6 +413,7 @@ impl MitigationEngine {
                 source: None,
                 source_prefix: None,
                 destination: Some(attack.target.ip),
+                destination_prefix: None,
                 protocol: Some(Protocol::Udp),
                 port: Some(p),
                 action: RuleAction::Drop,
@@ -297,35 +430,30 @@ impl MitigationEngine {
     // BGP Flowspec
     // =========================================================================
     
-    async fn activate_flowspec(&self, attack: &Attack) -> Vec<MitigationRule> {
+    async fn activate_flowspec(&self, mitigation_id: &str, attack: &Attack) -> Vec<MitigationRule> {
         if !self.auto_flowspec {
             return vec![];
         }
+        let Some(flowspec) = &self.flowspec else {
+            warn!("Flowspec requested for {} but no upstream peers are configured", attack.target.ip);
+            return vec![];
+        };
         
-        // Generate Flowspec rule
-        let flowspec = format!(
-            "flow4 {{
-                dst {}/32;
-                proto = {};
-                {}
-            }} then {{
-                rate-limit {};
-            }}",
-            attack.target.ip,
-            protocol_to_num(&attack.target.protocol),
-            if let Some(p) = attack.target.port { format!("dport = {};", p) } else { String::new() },
-            attack.metrics.total_bps / 100 // 1% of attack
-        );
+        let generator = FlowspecGenerator::new(flowspec.config().local_asn);
+        let rule = generator.generate(attack);
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
         
-        // Inject via BIRD
-        let cmd = format!("birdc configure soft \"{}\"", flowspec);
-        self.bird_exec(&cmd).await;
+        if let Err(e) = flowspec.announce(mitigation_id, vec![rule], Some(expires_at)).await {
+            warn!("Flowspec announcement for {} failed: {}", attack.target.ip, e);
+            return vec![];
+        }
         
         vec![MitigationRule {
             rule_type: RuleType::BgpFlowspec,
             source: None,
             source_prefix: None,
             destination: Some(attack.target.ip),
+            destination_prefix: None,
             protocol: Some(attack.target.protocol),
             port: attack.target.port,
             action: RuleAction::RateLimit,
@@ -335,7 +463,42 @@ impl MitigationEngine {
                 burst: 0,
             }),
             priority: 50,
-            expires_at: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            expires_at: Some(expires_at),
+        }]
+    }
+    
+    /// Divert the target's traffic into the scrubbing VRF via Flowspec redirect
+    async fn activate_scrubbing_redirect(&self, mitigation_id: &str, attack: &Attack) -> Vec<MitigationRule> {
+        let Some(flowspec) = &self.flowspec else {
+            warn!("Scrubbing requested for {} but Flowspec is not configured", attack.target.ip);
+            return vec![];
+        };
+        let Some(route_target) = flowspec.config().scrubbing_route_target.clone() else {
+            warn!("Scrubbing requested for {} but no scrubbing route target is set", attack.target.ip);
+            return vec![];
+        };
+        
+        let generator = FlowspecGenerator::new(flowspec.config().local_asn);
+        let rule = generator.generate_redirect(attack, &route_target);
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(4);
+        
+        if let Err(e) = flowspec.announce(mitigation_id, vec![rule], Some(expires_at)).await {
+            warn!("Scrubbing redirect for {} failed: {}", attack.target.ip, e);
+            return vec![];
+        }
+        
+        vec![MitigationRule {
+            rule_type: RuleType::BgpFlowspec,
+            source: None,
+            source_prefix: None,
+            destination: Some(attack.target.ip),
+            destination_prefix: None,
+            protocol: Some(attack.target.protocol),
+            port: attack.target.port,
+            action: RuleAction::Redirect,
+            rate_limit: None,
+            priority: 40,
+            expires_at: Some(expires_at),
         }]
     }
     
@@ -349,11 +512,10 @@ impl MitigationEngine {
             return vec![];
         }
         
-        // Announce /32 to blackhole community
-        let cmd = format!(
-            "birdc route add {}/32 blackhole community 65535:666",
-            attack.target.ip
-        );
+        // Announce /32, or the whole prefix under carpet bombing, to the
+        // blackhole community
+        let route = attack.target.cidr().unwrap_or_else(|| format!("{}/32", attack.target.ip));
+        let cmd = format!("birdc route add {} blackhole community 65535:666", route);
         self.bird_exec(&cmd).await;
         
         vec![MitigationRule {
@@ -361,6 +523,7 @@ impl MitigationEngine {
             source: None,
             source_prefix: None,
             destination: Some(attack.target.ip),
+            destination_prefix: None,
             protocol: None,
             port: None,
             action: RuleAction::Drop,
@@ -370,6 +533,37 @@ impl MitigationEngine {
         }]
     }
     
+    // =========================================================================
+    // L7 Challenges
+    // =========================================================================
+    
+    fn activate_challenge(&self, attack: &Attack) -> Vec<MitigationRule> {
+        let Some(challenges) = &self.challenges else {
+            warn!("No challenge service, HTTP flood on {} left to rate limiting", attack.target.ip);
+            return vec![];
+        };
+        
+        challenges.protect_destination(attack.target.ip, None);
+        // Known sources stay diverted wherever they go next
+        for source in attack.sources.iter().filter(|s| !s.is_spoofed) {
+            challenges.divert_client(source.ip);
+        }
+        
+        vec![MitigationRule {
+            rule_type: RuleType::L7Challenge,
+            source: None,
+            source_prefix: None,
+            destination: Some(attack.target.ip),
+            destination_prefix: None,
+            protocol: Some(Protocol::Tcp),
+            port: attack.target.port,
+            action: RuleAction::Challenge,
+            rate_limit: None,
+            priority: 60,
+            expires_at: None,
+        }]
+    }
+    
     // =========================================================================
     // Cleanup
     // =========================================================================
@@ -390,14 +584,56 @@ impl MitigationEngine {
     
     async fn remove_rtbh(&self, rule: &MitigationRule) {
         if let Some(dst) = rule.destination {
-            let cmd = format!("birdc route del {}/32 blackhole", dst);
+            let route = rule.destination_prefix.clone().unwrap_or_else(|| format!("{}/32", dst));
+            let cmd = format!("birdc route del {} blackhole", route);
             self.bird_exec(&cmd).await;
         }
     }
     
-    async fn remove_flowspec(&self, _rule: &MitigationRule) {
-        // Remove Flowspec via BIRD reconfigure
-        self.bird_exec("birdc configure").await;
+    async fn remove_syn_cookies(&self, rule: &MitigationRule) {
+        let Some(dst) = rule.destination else { return };
+        
+        match &self.xdp {
+            Some(xdp) if xdp.syn_cookies_enabled(&dst) => {
+                if let Err(e) = xdp.disable_syn_cookies(dst).await {
+                    warn!("Failed to disable XDP SYN cookies for {}: {}", dst, e);
+                }
+            }
+            _ => {
+                let cmd = format!("tcp syn-flood off for {}", dst);
+                self.vpp_exec(&cmd).await;
+            }
+        }
+    }
+    
+    async fn remove_syn_proxy(&self, rule: &MitigationRule) {
+        let Some(dst) = rule.destination else { return };
+        
+        match &self.xdp {
+            Some(xdp) if xdp.syn_protection(&dst).is_some_and(|p| p.mode == SynCookieMode::Handoff) => {
+                if let Err(e) = xdp.disable_syn_cookies(dst).await {
+                    warn!("Failed to disable XDP SYN proxy for {}: {}", dst, e);
+                }
+            }
+            _ => {
+                let cmd = format!("tcp session table syn-proxy off for {}", dst);
+                self.vpp_exec(&cmd).await;
+            }
+        }
+    }
+    
+    fn remove_challenge(&self, rule: &MitigationRule) {
+        if let (Some(challenges), Some(dst)) = (&self.challenges, rule.destination) {
+            challenges.release_destination(&dst);
+        }
+    }
+    
+    async fn remove_flowspec(&self, mitigation_id: &str) {
+        if let Some(flowspec) = &self.flowspec {
+            if let Err(e) = flowspec.withdraw(mitigation_id).await {
+                warn!("Flowspec withdrawal for mitigation {} failed: {}", mitigation_id, e);
+            }
+        }
     }
     
     // =========================================================================
@@ -457,3 +693,101 @@ fn protocol_to_num(proto: &Protocol) -> u8 {
         Protocol::Other(n) => *n,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AttackMetrics, AttackStatus, AttackTarget};
+    use std::collections::HashMap;
+
+    fn attack(attack_type: AttackType, ip: &str, prefix_len: Option<u8>) -> Attack {
+        let now = chrono::Utc::now();
+        Attack {
+            id: "attack-1".to_string(),
+            attack_type,
+            target: AttackTarget {
+                ip: ip.parse().unwrap(),
+                port: Some(443),
+                protocol: Protocol::Tcp,
+                customer_id: None,
+                prefix_len,
+            },
+            sources: Vec::new(),
+            metrics: AttackMetrics {
+                total_pps: 500_000,
+                total_bps: 4_000_000_000,
+                peak_pps: 500_000,
+                peak_bps: 4_000_000_000,
+                unique_sources: 1200,
+                avg_packet_size: 64,
+                protocol_distribution: HashMap::new(),
+            },
+            started_at: now,
+            last_seen: now,
+            status: AttackStatus::Detected,
+            mitigation: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_syn_rules_target_the_attacked_prefix() {
+        let engine = MitigationEngine::new();
+
+        let mitigation = engine.activate(&attack(AttackType::SynFlood, "203.0.113.0", Some(24))).await;
+        assert_eq!(mitigation.strategy, MitigationStrategy::SynCookie);
+        assert_eq!(mitigation.rules.len(), 1);
+        let rule = &mitigation.rules[0];
+        assert_eq!(rule.rule_type, RuleType::SynCookie);
+        assert_eq!(rule.destination, Some("203.0.113.0".parse().unwrap()));
+        assert_eq!(rule.destination_prefix.as_deref(), Some("203.0.113.0/24"));
+
+        let proxy = engine
+            .activate_with_strategy(&attack(AttackType::SynFlood, "203.0.113.10", None), MitigationStrategy::SynProxy)
+            .await;
+        assert_eq!(proxy.rules[0].rule_type, RuleType::SynProxy);
+        assert_eq!(proxy.rules[0].destination_prefix, None);
+    }
+
+    #[tokio::test]
+    async fn test_xdp_unavailable_falls_back_to_vpp() {
+        let xdp = Arc::new(XdpManager::new(Vec::new()));
+        let engine = MitigationEngine::new().with_xdp(xdp.clone());
+
+        // IPv6 is rejected by the XDP path before any map is touched
+        let target = "2001:db8::10";
+        let mut mitigation = engine.activate(&attack(AttackType::SynFlood, target, None)).await;
+        assert_eq!(mitigation.rules[0].rule_type, RuleType::SynCookie);
+        assert!(!xdp.syn_cookies_enabled(&target.parse().unwrap()));
+
+        // Counters of VPP-run mitigations are left alone
+        mitigation.stats.syn_cookies_sent = 7;
+        engine.refresh_stats(&mut mitigation).await;
+        assert_eq!(mitigation.stats.syn_cookies_sent, 7);
+        engine.deactivate(&mitigation).await;
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_scale_with_attack() {
+        let engine = MitigationEngine::new();
+        let mut udp = attack(AttackType::UdpFlood, "203.0.113.10", None);
+        udp.sources.push(crate::AttackSource {
+            ip: "198.51.100.7".parse().unwrap(),
+            network: None,
+            asn: None,
+            country: None,
+            pps: 250_000,
+            bps: 2_000_000_000,
+            is_spoofed: false,
+        });
+
+        let mitigation = engine.activate(&udp).await;
+        assert_eq!(mitigation.strategy, MitigationStrategy::RateLimit);
+        assert_eq!(mitigation.rules.len(), 2);
+
+        let global = mitigation.rules[0].rate_limit.as_ref().unwrap();
+        assert_eq!((global.pps, global.bps, global.burst), (Some(50_000), Some(400_000_000), 100_000));
+        let per_source = &mitigation.rules[1];
+        assert_eq!(per_source.source, Some("198.51.100.7".parse().unwrap()));
+        assert_eq!(per_source.rate_limit.as_ref().unwrap().pps, Some(500));
+    }
+}
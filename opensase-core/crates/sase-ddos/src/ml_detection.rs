@@ -391,6 +391,7 @@ impl MlDetector {
                 port: None,
                 protocol: Protocol::Tcp,
                 customer_id: None,
+                prefix_len: None,
             },
             sources: self.get_top_sources(&buffer, 10),
             metrics: AttackMetrics {
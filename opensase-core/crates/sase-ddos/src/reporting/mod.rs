@@ -0,0 +1,500 @@
+//! Attack Reporting
+//!
+//! Records a timeline for every attack: detection, classification,
+//! mitigation changes and a traffic curve at one-second granularity.
+//! Finished timelines are kept in a bounded ring so a post-mortem can be
+//! produced after the attack is over; [`crate::dashboard::Dashboard`]
+//! renders them as JSON or PDF.
+
+pub mod pdf;
+
+use crate::{ActiveMitigation, Attack, AttackSource, AttackType};
+use chrono::Timelike;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Sources kept on a timeline
+const TOP_SOURCES: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportingConfig {
+    /// Finished timelines kept before the oldest is dropped
+    pub retained_attacks: usize,
+    /// Seconds of traffic kept per attack; older seconds are dropped first
+    pub max_curve_seconds: usize,
+}
+
+impl Default for ReportingConfig {
+    fn default() -> Self {
+        Self {
+            retained_attacks: 256,
+            max_curve_seconds: 6 * 3600,
+        }
+    }
+}
+
+// =============================================================================
+// Timeline
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    Detected,
+    Classified,
+    MitigationActivated,
+    MitigationEscalated,
+    MitigationStopped,
+    Ended,
+}
+
+impl TimelineEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Detected => "detected",
+            Self::Classified => "classified",
+            Self::MitigationActivated => "mitigation_activated",
+            Self::MitigationEscalated => "mitigation_escalated",
+            Self::MitigationStopped => "mitigation_stopped",
+            Self::Ended => "ended",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub kind: TimelineEventKind,
+    pub details: String,
+}
+
+/// One second of attack traffic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficPoint {
+    /// Start of the second
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Highest rate reported during the second
+    pub pps: u64,
+    pub bps: u64,
+    /// Packets dropped and passed by mitigation during the second
+    pub packets_dropped: u64,
+    pub packets_passed: u64,
+}
+
+/// Everything recorded about one attack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackTimeline {
+    pub attack_id: String,
+    pub attack_type: AttackType,
+    pub target: String,
+    pub customer_id: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub events: Vec<TimelineEvent>,
+    pub curve: VecDeque<TrafficPoint>,
+    /// Set once seconds have been dropped from the start of the curve
+    pub curve_truncated: bool,
+    pub peak_pps: u64,
+    pub peak_bps: u64,
+    pub unique_sources: u64,
+    pub top_sources: Vec<AttackSource>,
+}
+
+impl AttackTimeline {
+    fn new(attack: &Attack) -> Self {
+        Self {
+            attack_id: attack.id.clone(),
+            attack_type: attack.attack_type,
+            target: attack.target.cidr().unwrap_or_else(|| attack.target.ip.to_string()),
+            customer_id: attack.target.customer_id.clone(),
+            started_at: attack.started_at,
+            ended_at: None,
+            events: Vec::new(),
+            curve: VecDeque::new(),
+            curve_truncated: false,
+            peak_pps: attack.metrics.peak_pps.max(attack.metrics.total_pps),
+            peak_bps: attack.metrics.peak_bps.max(attack.metrics.total_bps),
+            unique_sources: attack.metrics.unique_sources,
+            top_sources: top_sources(&attack.sources),
+        }
+    }
+
+    fn push(&mut self, kind: TimelineEventKind, details: String) {
+        self.events.push(TimelineEvent {
+            timestamp: chrono::Utc::now(),
+            kind,
+            details,
+        });
+    }
+
+    /// Seconds from detection to the end, or to now while ongoing
+    pub fn duration_seconds(&self) -> i64 {
+        let end = self.ended_at.unwrap_or_else(chrono::Utc::now);
+        (end - self.started_at).num_seconds()
+    }
+
+    pub fn packets_dropped(&self) -> u64 {
+        self.curve.iter().map(|p| p.packets_dropped).sum()
+    }
+
+    pub fn packets_passed(&self) -> u64 {
+        self.curve.iter().map(|p| p.packets_passed).sum()
+    }
+
+    /// Packets seen over the recorded curve
+    pub fn total_packets(&self) -> u64 {
+        self.curve.iter().map(|p| p.pps).sum()
+    }
+
+    /// Bytes seen over the recorded curve
+    pub fn total_bytes(&self) -> u64 {
+        self.curve.iter().map(|p| p.bps / 8).sum()
+    }
+
+    /// Share of mitigated packets that were dropped
+    pub fn effectiveness(&self) -> f64 {
+        let dropped = self.packets_dropped();
+        let passed = self.packets_passed();
+        if dropped + passed > 0 {
+            dropped as f64 / (dropped + passed) as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+fn top_sources(sources: &[AttackSource]) -> Vec<AttackSource> {
+    let mut top = sources.to_vec();
+    top.sort_by(|a, b| b.pps.cmp(&a.pps));
+    top.truncate(TOP_SOURCES);
+    top
+}
+
+// =============================================================================
+// Recorder
+// =============================================================================
+
+/// Timelines of ongoing attacks plus a ring of finished ones
+pub struct AttackRecorder {
+    config: ReportingConfig,
+    active: DashMap<String, AttackTimeline>,
+    /// Mitigation ID to the attack it was activated for
+    mitigations: DashMap<String, String>,
+    finished: Mutex<VecDeque<Arc<AttackTimeline>>>,
+}
+
+impl AttackRecorder {
+    pub fn new(config: ReportingConfig) -> Self {
+        Self {
+            finished: Mutex::new(VecDeque::with_capacity(config.retained_attacks)),
+            config,
+            active: DashMap::new(),
+            mitigations: DashMap::new(),
+        }
+    }
+
+    /// Start a timeline; repeated detections of the same attack only
+    /// refresh its peaks and sources
+    pub fn detected(&self, attack: &Attack) {
+        let mut timeline = self.active.entry(attack.id.clone()).or_insert_with(|| {
+            let mut timeline = AttackTimeline::new(attack);
+            timeline.push(
+                TimelineEventKind::Detected,
+                format!(
+                    "{:?} on {} at {} pps / {} bps from {} sources",
+                    attack.attack_type, timeline.target, attack.metrics.total_pps,
+                    attack.metrics.total_bps, attack.metrics.unique_sources,
+                ),
+            );
+            timeline
+        });
+        timeline.peak_pps = timeline.peak_pps.max(attack.metrics.total_pps);
+        timeline.peak_bps = timeline.peak_bps.max(attack.metrics.total_bps);
+        timeline.unique_sources = timeline.unique_sources.max(attack.metrics.unique_sources);
+        if !attack.sources.is_empty() {
+            timeline.top_sources = top_sources(&attack.sources);
+        }
+    }
+
+    /// Record the classifier's verdict on a detected attack
+    pub fn classified(&self, attack_id: &str, detected_as: AttackType, classified_as: AttackType) {
+        if let Some(mut timeline) = self.active.get_mut(attack_id) {
+            timeline.attack_type = classified_as;
+            let details = if detected_as == classified_as {
+                format!("Confirmed as {:?}", classified_as)
+            } else {
+                format!("Refined from {:?} to {:?}", detected_as, classified_as)
+            };
+            timeline.push(TimelineEventKind::Classified, details);
+        }
+    }
+
+    pub fn mitigation_activated(&self, attack_id: &str, mitigation: &ActiveMitigation) {
+        if let Some(mut timeline) = self.active.get_mut(attack_id) {
+            self.mitigations.insert(mitigation.id.clone(), attack_id.to_string());
+            timeline.push(
+                TimelineEventKind::MitigationActivated,
+                format!("{:?} with {} rules ({})", mitigation.strategy, mitigation.rules.len(), mitigation.id),
+            );
+        }
+    }
+
+    /// Record a mitigation's rules pushed upstream
+    pub fn mitigation_escalated(&self, mitigation_id: &str, routes: usize) {
+        self.with_mitigation(mitigation_id, |timeline| {
+            timeline.push(
+                TimelineEventKind::MitigationEscalated,
+                format!("{} Flowspec routes announced upstream ({})", routes, mitigation_id),
+            );
+        });
+    }
+
+    pub fn mitigation_stopped(&self, mitigation: &ActiveMitigation) {
+        self.with_mitigation(&mitigation.id, |timeline| {
+            timeline.push(
+                TimelineEventKind::MitigationStopped,
+                format!(
+                    "{:?} stopped after dropping {} packets ({})",
+                    mitigation.strategy, mitigation.stats.packets_dropped, mitigation.id,
+                ),
+            );
+        });
+        self.mitigations.remove(&mitigation.id);
+    }
+
+    fn with_mitigation(&self, mitigation_id: &str, f: impl FnOnce(&mut AttackTimeline)) {
+        let Some(attack_id) = self.mitigations.get(mitigation_id).map(|a| a.value().clone()) else {
+            return;
+        };
+        if let Some(mut timeline) = self.active.get_mut(&attack_id) {
+            f(&mut timeline);
+        }
+    }
+
+    /// Add a traffic report to the current second of the curve
+    ///
+    /// Rates keep the second's highest report; dropped and passed counts
+    /// are deltas and add up.
+    pub fn traffic(&self, attack_id: &str, pps: u64, bps: u64, dropped: u64, passed: u64) {
+        self.traffic_at(attack_id, chrono::Utc::now(), pps, bps, dropped, passed);
+    }
+
+    fn traffic_at(
+        &self,
+        attack_id: &str,
+        at: chrono::DateTime<chrono::Utc>,
+        pps: u64,
+        bps: u64,
+        dropped: u64,
+        passed: u64,
+    ) {
+        let Some(mut timeline) = self.active.get_mut(attack_id) else {
+            return;
+        };
+        let second = at.with_nanosecond(0).unwrap_or(at);
+        timeline.peak_pps = timeline.peak_pps.max(pps);
+        timeline.peak_bps = timeline.peak_bps.max(bps);
+
+        match timeline.curve.back_mut() {
+            Some(point) if point.timestamp == second => {
+                point.pps = point.pps.max(pps);
+                point.bps = point.bps.max(bps);
+                point.packets_dropped += dropped;
+                point.packets_passed += passed;
+            }
+            // Late reports for an earlier second are folded into the latest
+            Some(point) if point.timestamp > second => {
+                point.packets_dropped += dropped;
+                point.packets_passed += passed;
+            }
+            _ => {
+                timeline.curve.push_back(TrafficPoint {
+                    timestamp: second,
+                    pps,
+                    bps,
+                    packets_dropped: dropped,
+                    packets_passed: passed,
+                });
+                while timeline.curve.len() > self.config.max_curve_seconds {
+                    timeline.curve.pop_front();
+                    timeline.curve_truncated = true;
+                }
+            }
+        }
+    }
+
+    /// Close a timeline and move it to the ring of finished attacks
+    pub fn ended(&self, attack_id: &str) -> Option<Arc<AttackTimeline>> {
+        let (_, mut timeline) = self.active.remove(attack_id)?;
+        timeline.ended_at = Some(chrono::Utc::now());
+        let details = format!(
+            "Ended after {}s; peak {} pps / {} bps, {:.1}% of mitigated packets dropped",
+            timeline.duration_seconds(), timeline.peak_pps, timeline.peak_bps,
+            timeline.effectiveness() * 100.0,
+        );
+        timeline.push(TimelineEventKind::Ended, details);
+        self.mitigations.retain(|_, attack| attack.as_str() != attack_id);
+
+        let timeline = Arc::new(timeline);
+        let mut finished = self.finished.lock();
+        while finished.len() >= self.config.retained_attacks.max(1) {
+            finished.pop_front();
+        }
+        finished.push_back(timeline.clone());
+        Some(timeline)
+    }
+
+    /// Timeline of an ongoing or retained attack
+    pub fn timeline(&self, attack_id: &str) -> Option<AttackTimeline> {
+        if let Some(timeline) = self.active.get(attack_id) {
+            return Some(timeline.value().clone());
+        }
+        self.finished.lock()
+            .iter()
+            .rev()
+            .find(|t| t.attack_id == attack_id)
+            .map(|t| t.as_ref().clone())
+    }
+
+    /// Finished attacks, most recent first
+    pub fn finished(&self, limit: usize) -> Vec<Arc<AttackTimeline>> {
+        self.finished.lock().iter().rev().take(limit).cloned().collect()
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.active.len()
+    }
+}
+
+impl Default for AttackRecorder {
+    fn default() -> Self {
+        Self::new(ReportingConfig::default())
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AttackMetrics, AttackStatus, AttackTarget, MitigationStats, MitigationStrategy, Protocol};
+    use std::collections::HashMap;
+
+    fn attack(id: &str) -> Attack {
+        let now = chrono::Utc::now();
+        Attack {
+            id: id.to_string(),
+            attack_type: AttackType::UdpFlood,
+            target: AttackTarget {
+                ip: "203.0.113.10".parse().unwrap(),
+                port: None,
+                protocol: Protocol::Udp,
+                customer_id: None,
+                prefix_len: None,
+            },
+            sources: Vec::new(),
+            metrics: AttackMetrics {
+                total_pps: 500_000,
+                total_bps: 4_000_000_000,
+                peak_pps: 500_000,
+                peak_bps: 4_000_000_000,
+                unique_sources: 1200,
+                avg_packet_size: 1000,
+                protocol_distribution: HashMap::new(),
+            },
+            started_at: now,
+            last_seen: now,
+            status: AttackStatus::Detected,
+            mitigation: None,
+        }
+    }
+
+    fn mitigation(id: &str) -> ActiveMitigation {
+        ActiveMitigation {
+            id: id.to_string(),
+            strategy: MitigationStrategy::SourceBlock,
+            rules: Vec::new(),
+            started_at: chrono::Utc::now(),
+            stats: MitigationStats::default(),
+        }
+    }
+
+    #[test]
+    fn test_traffic_bucketed_per_second() {
+        let recorder = AttackRecorder::default();
+        recorder.detected(&attack("a1"));
+
+        let t0 = chrono::Utc::now().with_nanosecond(0).unwrap();
+        let mid = t0 + chrono::Duration::milliseconds(400);
+        recorder.traffic_at("a1", t0, 100, 800, 10, 5);
+        recorder.traffic_at("a1", mid, 300, 2400, 20, 5);
+        recorder.traffic_at("a1", t0 + chrono::Duration::seconds(1), 200, 1600, 30, 0);
+
+        let timeline = recorder.timeline("a1").unwrap();
+        assert_eq!(timeline.curve.len(), 2);
+        assert_eq!(timeline.curve[0].pps, 300);
+        assert_eq!(timeline.curve[0].packets_dropped, 30);
+        assert_eq!(timeline.packets_dropped(), 60);
+        assert_eq!(timeline.packets_passed(), 10);
+    }
+
+    #[test]
+    fn test_curve_capped() {
+        let recorder = AttackRecorder::new(ReportingConfig { max_curve_seconds: 3, ..Default::default() });
+        recorder.detected(&attack("a1"));
+
+        let t0 = chrono::Utc::now().with_nanosecond(0).unwrap();
+        for s in 0..5 {
+            recorder.traffic_at("a1", t0 + chrono::Duration::seconds(s), 100, 800, 0, 0);
+        }
+
+        let timeline = recorder.timeline("a1").unwrap();
+        assert_eq!(timeline.curve.len(), 3);
+        assert!(timeline.curve_truncated);
+        assert_eq!(timeline.curve[0].timestamp, t0 + chrono::Duration::seconds(2));
+    }
+
+    #[test]
+    fn test_mitigation_changes_on_timeline() {
+        let recorder = AttackRecorder::default();
+        recorder.detected(&attack("a1"));
+        recorder.classified("a1", AttackType::UdpFlood, AttackType::DnsAmplification);
+        recorder.mitigation_activated("a1", &mitigation("m1"));
+        recorder.mitigation_escalated("m1", 4);
+        recorder.mitigation_stopped(&mitigation("m1"));
+        // No longer attached to the attack
+        recorder.mitigation_escalated("m1", 4);
+
+        let timeline = recorder.ended("a1").unwrap();
+        let kinds: Vec<_> = timeline.events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![
+            TimelineEventKind::Detected,
+            TimelineEventKind::Classified,
+            TimelineEventKind::MitigationActivated,
+            TimelineEventKind::MitigationEscalated,
+            TimelineEventKind::MitigationStopped,
+            TimelineEventKind::Ended,
+        ]);
+        assert_eq!(timeline.attack_type, AttackType::DnsAmplification);
+        assert!(timeline.ended_at.is_some());
+    }
+
+    #[test]
+    fn test_finished_ring_evicts_oldest() {
+        let recorder = AttackRecorder::new(ReportingConfig { retained_attacks: 2, ..Default::default() });
+        for id in ["a1", "a2", "a3"] {
+            recorder.detected(&attack(id));
+            recorder.ended(id);
+        }
+
+        assert_eq!(recorder.active_count(), 0);
+        assert!(recorder.timeline("a1").is_none());
+        let finished: Vec<_> = recorder.finished(10).iter().map(|t| t.attack_id.clone()).collect();
+        assert_eq!(finished, vec!["a3", "a2"]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Minimal PDF Writer
+//!
+//! Just enough of PDF 1.4 for post-mortem reports: A4 pages of Helvetica
+//! text and stroked lines, uncompressed. Text is ASCII only; anything
+//! else is written as `?`.
+
+use std::fmt::Write;
+
+/// A4 in points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+        }
+    }
+}
+
+/// RGB stroke color, components in 0..=1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub f32, pub f32, pub f32);
+
+impl Color {
+    pub const BLACK: Color = Color(0.0, 0.0, 0.0);
+    pub const GRAY: Color = Color(0.6, 0.6, 0.6);
+}
+
+/// Pages are drawn in order; coordinates start at the bottom-left corner
+pub struct PdfDocument {
+    pages: Vec<String>,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        Self { pages: vec![String::new()] }
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(String::new());
+    }
+
+    fn content(&mut self) -> &mut String {
+        self.pages.last_mut().expect("document always has a page")
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, font: Font, size: f32, text: &str) {
+        let escaped = escape(text);
+        let _ = writeln!(
+            self.content(),
+            "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET",
+            font.resource(), size, x, y, escaped,
+        );
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: Color) {
+        self.polyline(&[from, to], width, color);
+    }
+
+    pub fn polyline(&mut self, points: &[(f32, f32)], width: f32, color: Color) {
+        let Some(((x, y), rest)) = points.split_first() else {
+            return;
+        };
+        let out = self.content();
+        let _ = writeln!(out, "{:.3} {:.3} {:.3} RG {:.2} w", color.0, color.1, color.2, width);
+        let _ = writeln!(out, "{:.2} {:.2} m", x, y);
+        for (x, y) in rest {
+            let _ = writeln!(out, "{:.2} {:.2} l", x, y);
+        }
+        out.push_str("S\n");
+    }
+
+    /// Serialize the document
+    pub fn finish(self) -> Vec<u8> {
+        // 1: catalog, 2: page tree, 3-4: fonts, then a page and its
+        // content stream per page
+        let page_ids: Vec<usize> = (0..self.pages.len()).map(|i| 5 + i * 2).collect();
+        let mut objects: Vec<String> = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+                page_ids.len(),
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_string(),
+        ];
+        for (page, content) in page_ids.iter().zip(&self.pages) {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT, page + 1,
+            ));
+            objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = writeln!(out, "{} 0 obj\n{}\nendobj", i + 1, object);
+        }
+
+        let xref = out.len();
+        let _ = writeln!(out, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = writeln!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF",
+            objects.len() + 1, xref,
+        );
+        out.into_bytes()
+    }
+}
+
+impl Default for PdfDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Escape a string literal; the output stays ASCII so byte offsets and
+/// stream lengths match
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xref_offsets_point_at_objects() {
+        let mut doc = PdfDocument::new();
+        doc.text(50.0, 800.0, Font::Bold, 16.0, "Post-mortem (draft) \\ é");
+        doc.new_page();
+        doc.line((50.0, 50.0), (100.0, 100.0), 1.0, Color::BLACK);
+        let bytes = doc.finish();
+        let pdf = String::from_utf8(bytes).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("(Post-mortem \\(draft\\) \\\\ ?) Tj"));
+        assert!(pdf.contains("/Count 2"));
+
+        let startxref: usize = pdf.rsplit("startxref\n").next().unwrap()
+            .lines().next().unwrap().parse().unwrap();
+        assert!(pdf[startxref..].starts_with("xref\n"));
+
+        let entries: Vec<usize> = pdf[startxref..].lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .map(|l| l[..10].parse().unwrap())
+            .collect();
+        assert_eq!(entries.len(), 8);
+        for (i, offset) in entries.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+}
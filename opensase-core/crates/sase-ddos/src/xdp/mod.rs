@@ -2,11 +2,20 @@
 //!
 //! eBPF-based packet filtering at 100M+ PPS directly in NIC driver.
 
+mod syncookie;
+
+pub use syncookie::{SynCookieConfig, SynCookieMode, SynCookieStats, SynProtection};
+
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::path::Path;
 use tracing::{info, warn, error};
 
+/// bpffs root; maps marked `LIBBPF_PIN_BY_NAME` are pinned here
+const BPF_FS: &str = "/sys/fs/bpf";
+/// Pinned program shared by all attached interfaces
+const PINNED_PROG: &str = "/sys/fs/bpf/xdp_ddos";
+
 /// XDP program manager for first-line DDoS defense
 pub struct XdpManager {
     /// Interface to attach XDP programs
@@ -17,6 +26,8 @@ pub struct XdpManager {
     blocklist: parking_lot::RwLock<HashMap<IpAddr, BlocklistEntry>>,
     /// Rate limit table
     rate_limits: parking_lot::RwLock<HashMap<IpAddr, RateLimitEntry>>,
+    /// SYN cookie protected destinations and secret generation
+    syn_cookies: syncookie::SynCookieState,
 }
 
 #[derive(Debug, Clone)]
@@ -54,42 +65,85 @@ impl XdpManager {
             program_path: "/opt/opensase/xdp".to_string(),
             blocklist: parking_lot::RwLock::new(HashMap::new()),
             rate_limits: parking_lot::RwLock::new(HashMap::new()),
+            syn_cookies: syncookie::SynCookieState::new(SynCookieConfig::default()),
         }
     }
     
+    /// Override the directory holding compiled XDP objects
+    pub fn with_program_path(mut self, path: impl Into<String>) -> Self {
+        self.program_path = path.into();
+        self
+    }
+    
+    /// Override SYN cookie secret rotation, verification TTL and protection
+    pub fn with_syn_cookie_config(mut self, config: SynCookieConfig) -> Self {
+        self.syn_cookies = syncookie::SynCookieState::new(config);
+        self
+    }
+    
     /// Load XDP program on all interfaces
     pub async fn load(&self) -> Result<(), String> {
+        let xdp_prog = format!("{}/ddos_filter.o", self.program_path);
+        
+        // Check if program exists
+        if !Path::new(&xdp_prog).exists() {
+            warn!("XDP program not found: {}", xdp_prog);
+            return Ok(()); // Non-fatal
+        }
+        
+        self.pin_program(&xdp_prog).await?;
+        
         for iface in &self.interfaces {
             self.load_interface(iface).await?;
         }
         
+        // Install the first cookie secret so SYN cookies can be enabled
+        self.rotate_syn_secret().await?;
+        
         info!("XDP programs loaded on {} interfaces", self.interfaces.len());
         Ok(())
     }
     
-    async fn load_interface(&self, interface: &str) -> Result<(), String> {
+    /// Load the object once and pin it; libbpf pins its maps by name
+    /// under bpffs so the control plane can reach them.
+    async fn pin_program(&self, xdp_prog: &str) -> Result<(), String> {
         use tokio::process::Command;
         
-        let xdp_prog = format!("{}/ddos_filter.o", self.program_path);
+        if Path::new(PINNED_PROG).exists() {
+            return Ok(());
+        }
         
-        // Check if program exists
-        if !Path::new(&xdp_prog).exists() {
-            warn!("XDP program not found: {}", xdp_prog);
-            return Ok(()); // Non-fatal
+        let output = Command::new("bpftool")
+            .args(["prog", "load", xdp_prog, PINNED_PROG, "type", "xdp"])
+            .output()
+            .await
+            .map_err(|e| format!("bpftool prog load failed: {}", e))?;
+        
+        if !output.status.success() {
+            return Err(format!(
+                "XDP program load failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
         }
         
-        // Load using ip link
+        Ok(())
+    }
+    
+    async fn load_interface(&self, interface: &str) -> Result<(), String> {
+        use tokio::process::Command;
+        
+        // Attach in native (driver) mode
         let output = Command::new("ip")
-            .args(["link", "set", "dev", interface, "xdp", "obj", &xdp_prog, "sec", "xdp_ddos"])
+            .args(["link", "set", "dev", interface, "xdp", "pinned", PINNED_PROG])
             .output()
             .await
             .map_err(|e| format!("Failed to load XDP: {}", e))?;
         
         if !output.status.success() {
             let err = String::from_utf8_lossy(&output.stderr);
-            // Try native mode
+            // Fall back to generic mode
             let output2 = Command::new("ip")
-                .args(["link", "set", "dev", interface, "xdpgeneric", "obj", &xdp_prog, "sec", "xdp_ddos"])
+                .args(["link", "set", "dev", interface, "xdpgeneric", "pinned", PINNED_PROG])
                 .output()
                 .await
                 .map_err(|e| format!("Failed to load XDP generic: {}", e))?;
@@ -117,6 +171,14 @@ impl XdpManager {
                 .await;
         }
         
+        // Maps stay pinned so blocklists and secrets survive a reload
+        let _ = tokio::fs::remove_file(PINNED_PROG).await;
+        
+        // Nothing hands cookie ACKs to the host stack any more
+        if let Err(e) = self.syn_cookies.restore_syncookies().await {
+            warn!("{}", e);
+        }
+        
         info!("XDP programs unloaded");
         Ok(())
     }
@@ -216,6 +278,8 @@ impl XdpManager {
         stats.packets_passed = parse_stat(&output, "passed").unwrap_or(0);
         stats.blocklist_hits = parse_stat(&output, "blocklist").unwrap_or(0);
         stats.rate_limit_hits = parse_stat(&output, "ratelimit").unwrap_or(0);
+        stats.syn_cookies_sent = parse_stat(&output, "syn_cookies_sent").unwrap_or(0);
+        stats.syn_cookies_validated = parse_stat(&output, "syn_verified").unwrap_or(0);
         
         Ok(stats)
     }
@@ -233,19 +297,21 @@ impl XdpManager {
     async fn update_bpf_map(&self, map_name: &str, key: &[u8], value: &[u8]) -> Result<(), String> {
         use tokio::process::Command;
         
-        let key_hex = hex::encode(key);
-        let value_hex = hex::encode(value);
-        
-        let map_path = format!("/sys/fs/bpf/{}", map_name);
+        let map_path = format!("{}/{}", BPF_FS, map_name);
         
         let output = Command::new("bpftool")
-            .args(["map", "update", "pinned", &map_path, "key", "hex", &key_hex, "value", "hex", &value_hex])
+            .args(["map", "update", "pinned", &map_path, "key", "hex"])
+            .args(hex_bytes(key))
+            .args(["value", "hex"])
+            .args(hex_bytes(value))
             .output()
             .await
             .map_err(|e| format!("bpftool failed: {}", e))?;
         
         if !output.status.success() {
-            warn!("BPF map update failed: {:?}", String::from_utf8_lossy(&output.stderr));
+            let err = String::from_utf8_lossy(&output.stderr);
+            warn!("BPF map update failed: {:?}", err);
+            return Err(format!("BPF map {} update failed: {}", map_name, err));
         }
         
         Ok(())
@@ -254,17 +320,43 @@ impl XdpManager {
     async fn delete_bpf_map(&self, map_name: &str, key: &[u8]) -> Result<(), String> {
         use tokio::process::Command;
         
-        let key_hex = hex::encode(key);
-        let map_path = format!("/sys/fs/bpf/{}", map_name);
+        let map_path = format!("{}/{}", BPF_FS, map_name);
         
         let _ = Command::new("bpftool")
-            .args(["map", "delete", "pinned", &map_path, "key", "hex", &key_hex])
+            .args(["map", "delete", "pinned", &map_path, "key", "hex"])
+            .args(hex_bytes(key))
             .output()
             .await;
         
         Ok(())
     }
     
+    /// Look up one key, returning bpftool's JSON (per-CPU maps carry a
+    /// `values` array with one entry per CPU)
+    async fn lookup_bpf_map(&self, map_name: &str, key: &[u8]) -> Result<serde_json::Value, String> {
+        use tokio::process::Command;
+        
+        let map_path = format!("{}/{}", BPF_FS, map_name);
+        
+        let output = Command::new("bpftool")
+            .args(["-j", "map", "lookup", "pinned", &map_path, "key", "hex"])
+            .args(hex_bytes(key))
+            .output()
+            .await
+            .map_err(|e| format!("bpftool lookup failed: {}", e))?;
+        
+        if !output.status.success() {
+            return Err(format!(
+                "BPF map {} lookup failed: {}",
+                map_name,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Invalid bpftool output: {}", e))
+    }
+    
     async fn update_bpf_lpm_map(&self, map_name: &str, key: &[u8], value: &[u8]) -> Result<(), String> {
         self.update_bpf_map(map_name, key, value).await
     }
@@ -272,7 +364,7 @@ impl XdpManager {
     async fn read_bpf_map(&self, map_name: &str) -> Result<String, String> {
         use tokio::process::Command;
         
-        let map_path = format!("/sys/fs/bpf/{}", map_name);
+        let map_path = format!("{}/{}", BPF_FS, map_name);
         
         let output = Command::new("bpftool")
             .args(["map", "dump", "pinned", &map_path])
@@ -294,6 +386,7 @@ pub struct XdpStats {
     pub blocklist_hits: u64,
     pub rate_limit_hits: u64,
     pub syn_cookies_sent: u64,
+    pub syn_cookies_validated: u64,
 }
 
 fn ip_to_key(ip: IpAddr) -> Vec<u8> {
@@ -303,6 +396,11 @@ fn ip_to_key(ip: IpAddr) -> Vec<u8> {
     }
 }
 
+/// bpftool takes `hex` keys and values as one argument per byte
+fn hex_bytes(bytes: &[u8]) -> Vec<String> {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 fn network_to_key(network: &ipnetwork::IpNetwork) -> Vec<u8> {
     let mut key = Vec::with_capacity(17);
     key.push(network.prefix()); // Prefix length first for LPM
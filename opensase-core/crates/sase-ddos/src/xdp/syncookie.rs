@@ -0,0 +1,560 @@
+//! XDP SYN Cookies
+//!
+//! Control plane for the stateless SYN cookie path in `ddos_filter.o`.
+//! SYNs to a protected destination are answered from XDP with a cookie.
+//! In [`SynCookieMode::Reset`] it is our own SipHash cookie, and a client
+//! that echoes it back is verified and reset so its retry reaches the
+//! server directly. In [`SynCookieMode::Handoff`] it is a kernel cookie,
+//! and the validated ACK is handed to the host stack, which opens the
+//! connection without a retry — a SYN proxy for servers on this host.
+//!
+//! Protection can be adaptive: with a SYN threshold set, the data plane
+//! only mints cookies while the destination's SYN rate is above it.
+//! Cookie secrets rotate on a timer and per-destination counters feed
+//! mitigation stats.
+
+use super::{ip_to_key, XdpManager};
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Pinned map names shared with `ddos_filter.c`
+const MAP_PROTECT: &str = "syn_protect";
+const MAP_STATS: &str = "syn_cookie_stats";
+const MAP_SECRETS: &str = "syn_secrets";
+const MAP_CONFIG: &str = "syn_config";
+const MAP_RATE: &str = "syn_rate";
+
+/// Host stack setting that accepts cookie ACKs it did not mint itself
+const TCP_SYNCOOKIES_SYSCTL: &str = "/proc/sys/net/ipv4/tcp_syncookies";
+
+/// Cookies carry a ~68s time slot and stay valid for one extra slot, so
+/// the previous secret must outlive that window.
+const MIN_ROTATION: Duration = Duration::from_secs(150);
+
+/// SYN cookie tuning
+#[derive(Debug, Clone)]
+pub struct SynCookieConfig {
+    /// How often the cookie secret rotates (clamped to at least 150s)
+    pub rotation_interval: Duration,
+    /// How long a verified source bypasses cookies
+    pub verified_ttl: Duration,
+    /// Protection applied by [`XdpManager::enable_syn_cookies`]
+    pub protection: SynProtection,
+}
+
+impl Default for SynCookieConfig {
+    fn default() -> Self {
+        Self {
+            rotation_interval: Duration::from_secs(300),
+            verified_ttl: Duration::from_secs(600),
+            protection: SynProtection::default(),
+        }
+    }
+}
+
+/// What happens to a client that returns a valid cookie
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SynCookieMode {
+    /// Verify and reset; the client's retry reaches the server
+    #[default]
+    Reset,
+    /// Pass the ACK to the host stack, which completes the connection.
+    /// The server must run on this host.
+    Handoff,
+}
+
+/// How a destination is protected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SynProtection {
+    pub mode: SynCookieMode,
+    /// SYNs/s before cookies kick in; `None` keeps them always on
+    pub syn_threshold_pps: Option<u32>,
+}
+
+impl SynProtection {
+    /// Encode `struct syn_protection { enabled_at, mode, syn_threshold_pps }`
+    fn encode(&self, enabled_at: u64) -> Vec<u8> {
+        let mode: u32 = match self.mode {
+            SynCookieMode::Reset => 0,
+            SynCookieMode::Handoff => 1,
+        };
+        let mut value = Vec::with_capacity(16);
+        value.extend_from_slice(&enabled_at.to_le_bytes());
+        value.extend_from_slice(&mode.to_le_bytes());
+        value.extend_from_slice(&self.syn_threshold_pps.unwrap_or(0).to_le_bytes());
+        value
+    }
+}
+
+/// Per-destination SYN cookie counters, summed across CPUs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SynCookieStats {
+    /// SYN-ACKs minted in XDP
+    pub cookies_sent: u64,
+    /// ACKs that echoed a valid cookie
+    pub cookies_validated: u64,
+    /// ACKs without a cookie, passed for pre-existing flows
+    pub unverified_acks: u64,
+    /// Packets from verified sources passed to the server
+    pub verified_passed: u64,
+    /// Validated ACKs handed to the host stack
+    pub handoffs: u64,
+    /// SYNs passed while below the adaptive threshold
+    pub syns_passed: u64,
+}
+
+/// Control-plane view of SYN cookie protection
+pub(super) struct SynCookieState {
+    config: SynCookieConfig,
+    /// Protected destinations, when protection started and how
+    protected: parking_lot::RwLock<HashMap<IpAddr, (chrono::DateTime<chrono::Utc>, SynProtection)>>,
+    /// Current secret generation; 0 until the first secret is installed
+    generation: tokio::sync::Mutex<u32>,
+    /// `tcp_syncookies` sysctl
+    sysctl: PathBuf,
+    /// Host setting before handoff changed it, restored once no
+    /// destination is in handoff mode
+    saved_syncookies: tokio::sync::Mutex<Option<String>>,
+}
+
+impl SynCookieState {
+    pub(super) fn new(mut config: SynCookieConfig) -> Self {
+        config.rotation_interval = config.rotation_interval.max(MIN_ROTATION);
+        Self {
+            config,
+            protected: parking_lot::RwLock::new(HashMap::new()),
+            generation: tokio::sync::Mutex::new(0),
+            sysctl: PathBuf::from(TCP_SYNCOOKIES_SYSCTL),
+            saved_syncookies: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Handoff ACKs carry cookies the host stack never minted; it only
+    /// accepts those with `tcp_syncookies=2`. The previous value is kept
+    /// for [`Self::restore_syncookies`].
+    async fn accept_foreign_cookies(&self) -> Result<(), String> {
+        let mut saved = self.saved_syncookies.lock().await;
+        let current = tokio::fs::read_to_string(&self.sysctl).await
+            .map_err(|e| format!("Failed to read {}: {}", self.sysctl.display(), e))?;
+        let current = current.trim();
+        if current == "2" {
+            return Ok(());
+        }
+        tokio::fs::write(&self.sysctl, "2").await
+            .map_err(|e| format!("Failed to set {}: {}", self.sysctl.display(), e))?;
+        // Keep the original value if something reset it behind our back
+        saved.get_or_insert_with(|| current.to_string());
+        info!("Host stack now accepts SYN cookie handoffs (tcp_syncookies=2, was {})", current);
+        Ok(())
+    }
+
+    /// Put `tcp_syncookies` back once no destination uses handoff
+    async fn release_handoff(&self) -> Result<(), String> {
+        let in_use = self.protected.read().values().any(|(_, p)| p.mode == SynCookieMode::Handoff);
+        if in_use {
+            return Ok(());
+        }
+        self.restore_syncookies().await
+    }
+
+    /// Restore the `tcp_syncookies` value saved when handoff was enabled
+    pub(super) async fn restore_syncookies(&self) -> Result<(), String> {
+        let mut saved = self.saved_syncookies.lock().await;
+        let Some(previous) = saved.as_deref() else {
+            return Ok(());
+        };
+        tokio::fs::write(&self.sysctl, previous).await
+            .map_err(|e| format!("Failed to restore {}: {}", self.sysctl.display(), e))?;
+        info!("Restored tcp_syncookies={}", previous);
+        *saved = None;
+        Ok(())
+    }
+}
+
+impl XdpManager {
+    // =========================================================================
+    // SYN Cookies
+    // =========================================================================
+
+    /// Answer SYNs to `dst` with XDP-minted cookies, as configured
+    pub async fn enable_syn_cookies(&self, dst: IpAddr) -> Result<(), String> {
+        self.enable_syn_protection(dst, self.syn_cookies.config.protection).await
+    }
+
+    /// Protect `dst` in a given mode; re-enabling switches the mode in place
+    pub async fn enable_syn_protection(&self, dst: IpAddr, protection: SynProtection) -> Result<(), String> {
+        if !dst.is_ipv4() {
+            return Err(format!("XDP SYN cookies are IPv4-only: {}", dst));
+        }
+
+        // The data plane fails open without a secret, so install one first
+        if *self.syn_cookies.generation.lock().await == 0 {
+            self.rotate_syn_secret().await?;
+        }
+
+        if protection.mode == SynCookieMode::Handoff {
+            self.syn_cookies.accept_foreign_cookies().await?;
+        }
+
+        let now = chrono::Utc::now();
+        let enabled_at = now.timestamp().max(0) as u64;
+        self.update_bpf_map(MAP_PROTECT, &ip_to_key(dst), &protection.encode(enabled_at)).await?;
+        let previous = self.syn_cookies.protected.write().insert(dst, (now, protection));
+        if previous.is_some_and(|(_, p)| p.mode == SynCookieMode::Handoff) {
+            self.syn_cookies.release_handoff().await?;
+        }
+
+        match protection.syn_threshold_pps {
+            Some(threshold) => info!(
+                "XDP SYN cookies ({:?}) armed for {} above {} SYN/s",
+                protection.mode, dst, threshold
+            ),
+            None => info!("XDP SYN cookies ({:?}) enabled for {}", protection.mode, dst),
+        }
+        Ok(())
+    }
+
+    /// Stop answering SYNs to `dst`; its counters are discarded
+    pub async fn disable_syn_cookies(&self, dst: IpAddr) -> Result<(), String> {
+        let key = ip_to_key(dst);
+        self.delete_bpf_map(MAP_PROTECT, &key).await?;
+        self.delete_bpf_map(MAP_STATS, &key).await?;
+        self.delete_bpf_map(MAP_RATE, &key).await?;
+        let removed = self.syn_cookies.protected.write().remove(&dst);
+        if removed.is_some_and(|(_, p)| p.mode == SynCookieMode::Handoff) {
+            self.syn_cookies.release_handoff().await?;
+        }
+
+        info!("XDP SYN cookies disabled for {}", dst);
+        Ok(())
+    }
+
+    /// Whether `dst` is under SYN cookie protection
+    pub fn syn_cookies_enabled(&self, dst: &IpAddr) -> bool {
+        self.syn_cookies.protected.read().contains_key(dst)
+    }
+
+    pub fn syn_cookie_config(&self) -> &SynCookieConfig {
+        &self.syn_cookies.config
+    }
+
+    /// How `dst` is protected, if it is
+    pub fn syn_protection(&self, dst: &IpAddr) -> Option<SynProtection> {
+        self.syn_cookies.protected.read().get(dst).map(|(_, p)| *p)
+    }
+
+    /// Destinations under SYN cookie protection
+    pub fn syn_cookie_targets(&self) -> Vec<IpAddr> {
+        self.syn_cookies.protected.read().keys().copied().collect()
+    }
+
+    /// Install a fresh cookie secret and make it current.
+    ///
+    /// The new secret goes into the slot not used by the current
+    /// generation before the generation is bumped, so cookies minted just
+    /// before rotation still validate against the previous secret.
+    pub async fn rotate_syn_secret(&self) -> Result<u32, String> {
+        let mut generation = self.syn_cookies.generation.lock().await;
+        let next = generation.wrapping_add(1).max(1);
+        let (k0, k1) = random_secret()?;
+
+        let slot = next & 1;
+        self.update_bpf_map(MAP_SECRETS, &slot.to_le_bytes(), &secret_value(k0, k1, next)).await?;
+
+        let ttl = self.syn_cookies.config.verified_ttl.as_secs().min(u32::MAX as u64) as u32;
+        let mut config = Vec::with_capacity(8);
+        config.extend_from_slice(&next.to_le_bytes());
+        config.extend_from_slice(&ttl.to_le_bytes());
+        self.update_bpf_map(MAP_CONFIG, &0u32.to_le_bytes(), &config).await?;
+
+        *generation = next;
+        debug!("SYN cookie secret rotated to generation {}", next);
+        Ok(next)
+    }
+
+    /// Read the SYN cookie counters for a protected destination
+    pub async fn syn_cookie_stats(&self, dst: IpAddr) -> Result<SynCookieStats, String> {
+        let value = self.lookup_bpf_map(MAP_STATS, &ip_to_key(dst)).await?;
+        parse_cookie_stats(&value)
+            .ok_or_else(|| format!("Unexpected {} entry for {}", MAP_STATS, dst))
+    }
+
+    /// Rotate the cookie secret on the configured interval
+    pub fn spawn_secret_rotation(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let period = self.syn_cookies.config.rotation_interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            // First tick fires immediately; load() already installed a secret
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.rotate_syn_secret().await {
+                    warn!("SYN cookie secret rotation failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// 128-bit SipHash key from the kernel CSPRNG
+fn random_secret() -> Result<(u64, u64), String> {
+    let mut buf = [0u8; 16];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut buf))
+        .map_err(|e| format!("Failed to read cookie secret: {}", e))?;
+
+    let mut k0 = [0u8; 8];
+    let mut k1 = [0u8; 8];
+    k0.copy_from_slice(&buf[..8]);
+    k1.copy_from_slice(&buf[8..]);
+    Ok((u64::from_le_bytes(k0), u64::from_le_bytes(k1)))
+}
+
+/// Encode `struct syn_secret { k0, k1, generation, pad }`
+fn secret_value(k0: u64, k1: u64, generation: u32) -> Vec<u8> {
+    let mut value = Vec::with_capacity(24);
+    value.extend_from_slice(&k0.to_le_bytes());
+    value.extend_from_slice(&k1.to_le_bytes());
+    value.extend_from_slice(&generation.to_le_bytes());
+    value.extend_from_slice(&0u32.to_le_bytes());
+    value
+}
+
+/// Sum a per-CPU `struct syn_cookie_stats` lookup from `bpftool -j`.
+/// Each CPU entry's `value` is an array of "0xNN" byte strings.
+fn parse_cookie_stats(lookup: &serde_json::Value) -> Option<SynCookieStats> {
+    let cpus = lookup.get("values")?.as_array()?;
+    let mut stats = SynCookieStats::default();
+
+    for cpu in cpus {
+        let bytes: Vec<u8> = cpu
+            .get("value")?
+            .as_array()?
+            .iter()
+            .map(|b| b.as_str().and_then(|s| u8::from_str_radix(s.trim_start_matches("0x"), 16).ok()))
+            .collect::<Option<_>>()?;
+
+        if bytes.len() < 32 {
+            return None;
+        }
+        let field = |i: usize| {
+            let mut raw = [0u8; 8];
+            raw.copy_from_slice(&bytes[i * 8..i * 8 + 8]);
+            u64::from_le_bytes(raw)
+        };
+
+        stats.cookies_sent += field(0);
+        stats.cookies_validated += field(1);
+        stats.unverified_acks += field(2);
+        stats.verified_passed += field(3);
+        // Objects built before handoff support carry four counters
+        if bytes.len() >= 48 {
+            stats.handoffs += field(4);
+            stats.syns_passed += field(5);
+        }
+    }
+
+    Some(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mirror of the cookie in `ddos_filter.c`; keep the layout in sync
+    const COOKIE_GEN_SHIFT: u32 = 31;
+    const COOKIE_SLOT_SHIFT: u32 = 26;
+    const COOKIE_SLOT_MASK: u32 = 0x1F;
+    const COOKIE_HASH_MASK: u32 = 0x3FF_FFFF;
+    const COOKIE_MAX_AGE: u32 = 1;
+
+    fn syn_cookie_hash(k0: u64, k1: u64, saddr: u32, daddr: u32, sport: u16, dport: u16, slot: u32) -> u32 {
+        let mut v = [
+            0x736f6d6570736575u64 ^ k0,
+            0x646f72616e646f6du64 ^ k1,
+            0x6c7967656e657261u64 ^ k0,
+            0x7465646279746573u64 ^ k1,
+        ];
+        let round = |v: &mut [u64; 4]| {
+            v[0] = v[0].wrapping_add(v[1]);
+            v[1] = v[1].rotate_left(13) ^ v[0];
+            v[0] = v[0].rotate_left(32);
+            v[2] = v[2].wrapping_add(v[3]);
+            v[3] = v[3].rotate_left(16) ^ v[2];
+            v[0] = v[0].wrapping_add(v[3]);
+            v[3] = v[3].rotate_left(21) ^ v[0];
+            v[2] = v[2].wrapping_add(v[1]);
+            v[1] = v[1].rotate_left(17) ^ v[2];
+            v[2] = v[2].rotate_left(32);
+        };
+
+        let m0 = ((saddr as u64) << 32) | daddr as u64;
+        let m1 = ((sport as u64) << 48) | ((dport as u64) << 32) | slot as u64;
+        for m in [m0, m1, 16u64 << 56] {
+            v[3] ^= m;
+            round(&mut v);
+            round(&mut v);
+            v[0] ^= m;
+        }
+        v[2] ^= 0xff;
+        for _ in 0..4 {
+            round(&mut v);
+        }
+        (v[0] ^ v[1] ^ v[2] ^ v[3]) as u32
+    }
+
+    struct Tuple(u32, u32, u16, u16);
+
+    const CLIENT: Tuple = Tuple(0xC633_6401, 0x0A00_0005, 51_000, 443);
+
+    fn mint(key: (u64, u64), generation: u32, slot: u32, t: &Tuple) -> u32 {
+        let hash = syn_cookie_hash(key.0, key.1, t.0, t.1, t.2, t.3, slot);
+        ((generation & 1) << COOKIE_GEN_SHIFT) | (slot << COOKIE_SLOT_SHIFT) | (hash & COOKIE_HASH_MASK)
+    }
+
+    /// `secrets` is indexed by generation parity, like the `syn_secrets` map
+    fn check(cookie: u32, secrets: &[(u64, u64); 2], now_slot: u32, t: &Tuple) -> bool {
+        let (k0, k1) = secrets[(cookie >> COOKIE_GEN_SHIFT) as usize];
+        let slot = (cookie >> COOKIE_SLOT_SHIFT) & COOKIE_SLOT_MASK;
+        if now_slot.wrapping_sub(slot) & COOKIE_SLOT_MASK > COOKIE_MAX_AGE {
+            return false;
+        }
+        let hash = syn_cookie_hash(k0, k1, t.0, t.1, t.2, t.3, slot);
+        hash & COOKIE_HASH_MASK == cookie & COOKIE_HASH_MASK
+    }
+
+    #[test]
+    fn test_siphash_matches_reference_vector() {
+        // SipHash-2-4 test vector: key 00..0f, message 00..0f
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let m0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let m1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let hash = syn_cookie_hash(
+            k0, k1,
+            (m0 >> 32) as u32, m0 as u32,
+            (m1 >> 48) as u16, (m1 >> 32) as u16, m1 as u32,
+        );
+        assert_eq!(hash, 0x3f2acc7f57c29bdbu64 as u32);
+    }
+
+    #[test]
+    fn test_cookie_round_trip() {
+        let secrets = [(0x1111, 0x2222), (0x3333, 0x4444)];
+        for generation in [2, 3] {
+            let cookie = mint(secrets[(generation & 1) as usize], generation, 7, &CLIENT);
+            assert_eq!(cookie >> COOKIE_GEN_SHIFT, generation & 1);
+            assert_eq!((cookie >> COOKIE_SLOT_SHIFT) & COOKIE_SLOT_MASK, 7);
+            assert!(check(cookie, &secrets, 7, &CLIENT));
+            // Still valid one slot later, as the rotation interval assumes
+            assert!(check(cookie, &secrets, 8, &CLIENT));
+        }
+    }
+
+    #[test]
+    fn test_cookie_rejected() {
+        let secrets = [(0x1111, 0x2222), (0x3333, 0x4444)];
+        let cookie = mint(secrets[0], 4, 7, &CLIENT);
+
+        assert!(!check(cookie, &secrets, 9, &CLIENT), "expired after two slots");
+        assert!(!check(cookie, &secrets, 6, &CLIENT), "slot from the future");
+        assert!(!check(cookie, &secrets, 7, &Tuple(CLIENT.0, CLIENT.1, CLIENT.2 + 1, CLIENT.3)));
+        assert!(!check(cookie, &secrets, 7, &Tuple(CLIENT.0 + 1, CLIENT.1, CLIENT.2, CLIENT.3)));
+        assert!(!check(cookie, &[(0x5555, 0x6666), secrets[1]], 7, &CLIENT), "rotated-out secret");
+        assert!(!check(cookie ^ 1, &secrets, 7, &CLIENT), "tampered hash bits");
+    }
+
+    #[test]
+    fn test_cookie_slot_wraps() {
+        let secrets = [(0xAAAA, 0xBBBB), (0xCCCC, 0xDDDD)];
+        let cookie = mint(secrets[1], 1, COOKIE_SLOT_MASK, &CLIENT);
+        assert!(check(cookie, &secrets, 0, &CLIENT));
+        assert!(!check(cookie, &secrets, 1, &CLIENT));
+    }
+
+    #[test]
+    fn test_map_value_layouts() {
+        let value = secret_value(0x0102030405060708, 0x1112131415161718, 5);
+        assert_eq!(value.len(), 24);
+        assert_eq!(&value[..8], &0x0102030405060708u64.to_le_bytes());
+        assert_eq!(&value[8..16], &0x1112131415161718u64.to_le_bytes());
+        assert_eq!(&value[16..], &[5, 0, 0, 0, 0, 0, 0, 0]);
+
+        let protection = SynProtection { mode: SynCookieMode::Handoff, syn_threshold_pps: Some(1000) };
+        let value = protection.encode(1_700_000_000);
+        assert_eq!(value.len(), 16);
+        assert_eq!(&value[..8], &1_700_000_000u64.to_le_bytes());
+        assert_eq!(&value[8..12], &1u32.to_le_bytes());
+        assert_eq!(&value[12..], &1000u32.to_le_bytes());
+        assert_eq!(&SynProtection::default().encode(0)[8..], &[0; 8]);
+    }
+
+    fn cpu_entry(counters: &[u64]) -> serde_json::Value {
+        let bytes: Vec<String> = counters.iter()
+            .flat_map(|c| c.to_le_bytes())
+            .map(|b| format!("0x{:02x}", b))
+            .collect();
+        serde_json::json!({ "cpu": 0, "value": bytes })
+    }
+
+    #[test]
+    fn test_parse_cookie_stats() {
+        let lookup = serde_json::json!({ "values": [
+            cpu_entry(&[1, 2, 3, 4, 5, 6]),
+            cpu_entry(&[10, 20, 30, 40, 50, 60]),
+        ]});
+        assert_eq!(parse_cookie_stats(&lookup), Some(SynCookieStats {
+            cookies_sent: 11,
+            cookies_validated: 22,
+            unverified_acks: 33,
+            verified_passed: 44,
+            handoffs: 55,
+            syns_passed: 66,
+        }));
+
+        let legacy = serde_json::json!({ "values": [cpu_entry(&[1, 2, 3, 0x100])] });
+        let stats = parse_cookie_stats(&legacy).unwrap();
+        assert_eq!((stats.verified_passed, stats.handoffs), (0x100, 0));
+
+        let short = serde_json::json!({ "values": [cpu_entry(&[1, 2, 3])] });
+        assert_eq!(parse_cookie_stats(&short), None);
+    }
+
+    #[tokio::test]
+    async fn test_syncookies_sysctl_restored() {
+        let sysctl = std::env::temp_dir().join(format!("tcp_syncookies-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&sysctl, "1\n").unwrap();
+        let mut state = SynCookieState::new(SynCookieConfig::default());
+        state.sysctl = sysctl.clone();
+
+        state.accept_foreign_cookies().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&sysctl).unwrap(), "2");
+        // Re-enabling keeps the original value
+        state.accept_foreign_cookies().await.unwrap();
+
+        let dst: IpAddr = "10.0.0.5".parse().unwrap();
+        let handoff = SynProtection { mode: SynCookieMode::Handoff, syn_threshold_pps: None };
+        state.protected.write().insert(dst, (chrono::Utc::now(), handoff));
+        state.release_handoff().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&sysctl).unwrap(), "2", "still in use");
+
+        state.protected.write().remove(&dst);
+        state.release_handoff().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&sysctl).unwrap(), "1");
+
+        // Nothing saved when the host already accepted foreign cookies
+        std::fs::write(&sysctl, "2").unwrap();
+        state.accept_foreign_cookies().await.unwrap();
+        state.restore_syncookies().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&sysctl).unwrap(), "2");
+
+        std::fs::remove_file(&sysctl).unwrap();
+    }
+}
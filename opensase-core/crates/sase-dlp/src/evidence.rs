@@ -0,0 +1,541 @@
+//! Evidence capture for DLP incidents
+//!
+//! Turns ephemeral [`ScanResult`] matches into reviewable incidents:
+//!
+//! - Each match keeps a context window around it, with every sensitive
+//!   value inside the window redacted to its last four characters.
+//! - Incidents link back to the flow, email or file that triggered them
+//!   and expire according to severity-based retention rules.
+//! - Raw values are sealed away from the analyst view and can only be
+//!   revealed through a time-limited grant approved by a second person.
+
+use crate::{ScanResult, Severity};
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Object that triggered the scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvidenceSource {
+    /// Network flow inspected inline
+    Flow {
+        /// Flow identifier
+        flow_id: String,
+        /// User the flow was attributed to
+        user: Option<String>,
+    },
+    /// Email message
+    Email {
+        /// Message-ID header
+        message_id: String,
+        /// Envelope sender
+        sender: String,
+    },
+    /// Uploaded or stored file
+    File {
+        /// File path or object key
+        path: String,
+        /// SHA-256 of the file content
+        sha256: Option<String>,
+    },
+}
+
+/// How long incidents are kept, by highest severity
+#[derive(Debug, Clone)]
+pub struct RetentionRules {
+    /// Retention in seconds per severity
+    pub by_severity: HashMap<Severity, u64>,
+    /// Retention when a severity has no entry
+    pub default_secs: u64,
+}
+
+impl Default for RetentionRules {
+    fn default() -> Self {
+        const DAY: u64 = 86_400;
+        let by_severity = HashMap::from([
+            (Severity::Info, 7 * DAY),
+            (Severity::Low, 30 * DAY),
+            (Severity::Medium, 90 * DAY),
+            (Severity::High, 180 * DAY),
+            (Severity::Critical, 365 * DAY),
+        ]);
+        Self { by_severity, default_secs: 90 * DAY }
+    }
+}
+
+impl RetentionRules {
+    /// Retention period for an incident of this severity
+    pub fn retention_secs(&self, severity: Severity) -> u64 {
+        self.by_severity.get(&severity).copied().unwrap_or(self.default_secs)
+    }
+}
+
+/// Evidence capture configuration
+#[derive(Debug, Clone)]
+pub struct EvidenceConfig {
+    /// Characters of context kept on each side of a match
+    pub context_chars: usize,
+    /// Retention rules
+    pub retention: RetentionRules,
+    /// Users allowed to approve unmask requests
+    pub approvers: HashSet<String>,
+    /// Longest lifetime of an unmask grant in seconds
+    pub max_grant_secs: u64,
+}
+
+impl Default for EvidenceConfig {
+    fn default() -> Self {
+        Self {
+            context_chars: 32,
+            retention: RetentionRules::default(),
+            approvers: HashSet::new(),
+            max_grant_secs: 3600,
+        }
+    }
+}
+
+impl EvidenceConfig {
+    /// Allow `user` to approve unmask requests
+    pub fn with_approver(mut self, user: &str) -> Self {
+        self.approvers.insert(user.to_string());
+        self
+    }
+
+    /// Set context window size
+    pub fn with_context_chars(mut self, chars: usize) -> Self {
+        self.context_chars = chars;
+        self
+    }
+
+    /// Set retention rules
+    pub fn with_retention(mut self, retention: RetentionRules) -> Self {
+        self.retention = retention;
+        self
+    }
+}
+
+/// Analyst-safe view of one match
+#[derive(Debug, Clone)]
+pub struct MatchEvidence {
+    /// Classifier ID
+    pub classifier_id: u32,
+    /// Classifier name
+    pub classifier_name: String,
+    /// Severity
+    pub severity: Severity,
+    /// Confidence (0.0 - 1.0)
+    pub confidence: f64,
+    /// Byte offset of the match in the scanned content
+    pub offset: usize,
+    /// Redacted value, e.g. `****-****-****-1111`
+    pub redacted: String,
+    /// Truncated SHA-256 of the raw value for correlating repeats
+    pub fingerprint: String,
+    /// Redacted text preceding the match
+    pub context_before: String,
+    /// Redacted text following the match
+    pub context_after: String,
+}
+
+/// Stored DLP incident with redacted evidence
+#[derive(Debug, Clone)]
+pub struct Incident {
+    /// Incident ID
+    pub id: u64,
+    /// What triggered the scan
+    pub source: EvidenceSource,
+    /// Highest match severity
+    pub severity: Severity,
+    /// Capture time (unix seconds)
+    pub created_at: u64,
+    /// Purge time (unix seconds)
+    pub expires_at: u64,
+    /// Exempt from retention purges
+    pub legal_hold: bool,
+    /// Redacted match evidence
+    pub evidence: Vec<MatchEvidence>,
+}
+
+/// Pending or approved request to see raw values
+#[derive(Debug, Clone)]
+pub struct UnmaskRequest {
+    /// Request ID
+    pub id: u64,
+    /// Incident the request covers
+    pub incident_id: u64,
+    /// Analyst asking for access
+    pub requested_by: String,
+    /// Justification
+    pub reason: String,
+    /// Approver, once approved
+    pub approved_by: Option<String>,
+    /// Grant expiry (unix seconds), once approved
+    pub expires_at: Option<u64>,
+}
+
+/// Audit trail entry for access to sealed values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditEvent {
+    /// Unmask requested
+    Requested {
+        /// Request ID
+        request_id: u64,
+        /// Analyst
+        user: String,
+    },
+    /// Unmask approved
+    Approved {
+        /// Request ID
+        request_id: u64,
+        /// Approver
+        user: String,
+    },
+    /// Raw value revealed
+    Revealed {
+        /// Incident ID
+        incident_id: u64,
+        /// Match index within the incident
+        match_index: usize,
+        /// Analyst
+        user: String,
+    },
+}
+
+/// Evidence errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EvidenceError {
+    /// Unknown incident
+    #[error("incident {0} not found")]
+    IncidentNotFound(u64),
+    /// Unknown unmask request
+    #[error("unmask request {0} not found")]
+    RequestNotFound(u64),
+    /// Match index out of range
+    #[error("incident has no match at index {0}")]
+    MatchNotFound(usize),
+    /// Approver is not allowed to approve
+    #[error("{0} is not an evidence approver")]
+    NotApprover(String),
+    /// Requester tried to approve their own request
+    #[error("unmask requests need a second approver")]
+    SelfApproval,
+    /// No active approved grant for this analyst
+    #[error("no approved unmask grant for {0}")]
+    NotAuthorized(String),
+}
+
+struct IncidentRecord {
+    incident: Incident,
+    /// Raw matched values, index-aligned with `incident.evidence`
+    sealed: Vec<String>,
+}
+
+/// Incident and evidence store
+pub struct EvidenceStore {
+    config: EvidenceConfig,
+    incidents: RwLock<HashMap<u64, IncidentRecord>>,
+    requests: RwLock<HashMap<u64, UnmaskRequest>>,
+    audit: RwLock<Vec<AuditEvent>>,
+    next_id: AtomicU64,
+}
+
+impl EvidenceStore {
+    /// Create an empty store
+    pub fn new(config: EvidenceConfig) -> Self {
+        Self {
+            config,
+            incidents: RwLock::new(HashMap::new()),
+            requests: RwLock::new(HashMap::new()),
+            audit: RwLock::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Record an incident for `result`; `None` when nothing matched.
+    ///
+    /// `content` must be the text that produced `result`.
+    pub fn capture(&self, source: EvidenceSource, content: &str, result: &ScanResult) -> Option<u64> {
+        let severity = result.highest_severity?;
+        let now = unix_now();
+
+        let evidence = result.matches.iter()
+            .map(|m| MatchEvidence {
+                classifier_id: m.classifier_id,
+                classifier_name: m.classifier_name.clone(),
+                severity: m.severity,
+                confidence: m.confidence,
+                offset: m.start,
+                redacted: redact_value(&m.matched_text),
+                fingerprint: fingerprint(&m.matched_text),
+                context_before: redacted_window(content, result, m.start.saturating_sub(self.config.context_chars), m.start),
+                context_after: redacted_window(content, result, m.end, m.end + self.config.context_chars),
+            })
+            .collect();
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let incident = Incident {
+            id,
+            source,
+            severity,
+            created_at: now,
+            expires_at: now + self.config.retention.retention_secs(severity),
+            legal_hold: false,
+            evidence,
+        };
+        let sealed = result.matches.iter().map(|m| m.matched_text.clone()).collect();
+
+        tracing::info!(incident = id, ?severity, matches = result.matches.len(), "DLP evidence captured");
+        self.incidents.write().insert(id, IncidentRecord { incident, sealed });
+        Some(id)
+    }
+
+    /// Redacted incident
+    pub fn get(&self, id: u64) -> Option<Incident> {
+        self.incidents.read().get(&id).map(|r| r.incident.clone())
+    }
+
+    /// All incidents, newest first
+    pub fn list(&self) -> Vec<Incident> {
+        let mut incidents: Vec<_> = self.incidents.read().values()
+            .map(|r| r.incident.clone())
+            .collect();
+        incidents.sort_by_key(|i| std::cmp::Reverse(i.id));
+        incidents
+    }
+
+    /// Incidents triggered by `source`
+    pub fn for_source(&self, source: &EvidenceSource) -> Vec<Incident> {
+        self.list().into_iter().filter(|i| &i.source == source).collect()
+    }
+
+    /// Place or lift a legal hold
+    pub fn set_legal_hold(&self, id: u64, hold: bool) -> Result<(), EvidenceError> {
+        let mut incidents = self.incidents.write();
+        let record = incidents.get_mut(&id).ok_or(EvidenceError::IncidentNotFound(id))?;
+        record.incident.legal_hold = hold;
+        Ok(())
+    }
+
+    /// Drop expired incidents not under legal hold
+    pub fn purge_expired(&self) -> usize {
+        let now = unix_now();
+        let mut incidents = self.incidents.write();
+        let before = incidents.len();
+        incidents.retain(|_, r| r.incident.legal_hold || r.incident.expires_at > now);
+        let purged = before - incidents.len();
+        drop(incidents);
+
+        if purged > 0 {
+            let live = self.incidents.read();
+            self.requests.write().retain(|_, r| live.contains_key(&r.incident_id));
+        }
+        purged
+    }
+
+    /// Ask to see the raw values of an incident
+    pub fn request_unmask(&self, incident_id: u64, analyst: &str, reason: &str) -> Result<u64, EvidenceError> {
+        if !self.incidents.read().contains_key(&incident_id) {
+            return Err(EvidenceError::IncidentNotFound(incident_id));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.requests.write().insert(id, UnmaskRequest {
+            id,
+            incident_id,
+            requested_by: analyst.to_string(),
+            reason: reason.to_string(),
+            approved_by: None,
+            expires_at: None,
+        });
+        self.audit.write().push(AuditEvent::Requested { request_id: id, user: analyst.to_string() });
+        Ok(id)
+    }
+
+    /// Approve a request for up to `ttl_secs` (capped by config)
+    pub fn approve_unmask(&self, request_id: u64, approver: &str, ttl_secs: u64) -> Result<(), EvidenceError> {
+        if !self.config.approvers.contains(approver) {
+            return Err(EvidenceError::NotApprover(approver.to_string()));
+        }
+
+        let mut requests = self.requests.write();
+        let request = requests.get_mut(&request_id).ok_or(EvidenceError::RequestNotFound(request_id))?;
+        if request.requested_by == approver {
+            return Err(EvidenceError::SelfApproval);
+        }
+
+        request.approved_by = Some(approver.to_string());
+        request.expires_at = Some(unix_now() + ttl_secs.min(self.config.max_grant_secs));
+        drop(requests);
+
+        self.audit.write().push(AuditEvent::Approved { request_id, user: approver.to_string() });
+        Ok(())
+    }
+
+    /// Pending (unapproved) unmask requests
+    pub fn pending_requests(&self) -> Vec<UnmaskRequest> {
+        self.requests.read().values()
+            .filter(|r| r.approved_by.is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// Raw value of one match; needs an active approved grant
+    pub fn reveal(&self, incident_id: u64, match_index: usize, analyst: &str) -> Result<String, EvidenceError> {
+        let now = unix_now();
+        let granted = self.requests.read().values().any(|r| {
+            r.incident_id == incident_id
+                && r.requested_by == analyst
+                && r.approved_by.is_some()
+                && r.expires_at.map(|t| t > now).unwrap_or(false)
+        });
+        if !granted {
+            return Err(EvidenceError::NotAuthorized(analyst.to_string()));
+        }
+
+        let incidents = self.incidents.read();
+        let record = incidents.get(&incident_id).ok_or(EvidenceError::IncidentNotFound(incident_id))?;
+        let value = record.sealed.get(match_index).cloned().ok_or(EvidenceError::MatchNotFound(match_index))?;
+        drop(incidents);
+
+        tracing::warn!(incident = incident_id, user = analyst, "DLP evidence unmasked");
+        self.audit.write().push(AuditEvent::Revealed { incident_id, match_index, user: analyst.to_string() });
+        Ok(value)
+    }
+
+    /// Access audit trail
+    pub fn audit_log(&self) -> Vec<AuditEvent> {
+        self.audit.read().clone()
+    }
+
+    /// Number of stored incidents
+    pub fn len(&self) -> usize {
+        self.incidents.read().len()
+    }
+
+    /// Whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for EvidenceStore {
+    fn default() -> Self {
+        Self::new(EvidenceConfig::default())
+    }
+}
+
+/// Mask all but the last four alphanumerics, keeping separators:
+/// `4111-1111-1111-1111` becomes `****-****-****-1111`.
+/// Values with fewer than eight alphanumerics are fully masked.
+pub fn redact_value(value: &str) -> String {
+    let total = value.chars().filter(|c| c.is_alphanumeric()).count();
+    let keep = if total >= 8 { 4 } else { 0 };
+
+    let mut seen = 0;
+    value.chars()
+        .map(|c| {
+            if !c.is_alphanumeric() {
+                return c;
+            }
+            seen += 1;
+            if seen > total - keep { c } else { '*' }
+        })
+        .collect()
+}
+
+fn fingerprint(value: &str) -> String {
+    hex::encode(&Sha256::digest(value.as_bytes())[..8])
+}
+
+/// Slice `content[start..end]` on char boundaries, redacting every match
+/// that overlaps the window
+fn redacted_window(content: &str, result: &ScanResult, start: usize, end: usize) -> String {
+    let mut start = start.min(content.len());
+    let mut end = end.min(content.len());
+    while !content.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !content.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut out = String::with_capacity(end - start);
+    let mut pos = start;
+    let mut overlapping: Vec<_> = result.matches.iter()
+        .filter(|m| m.start < end && m.end > start)
+        .collect();
+    overlapping.sort_by_key(|m| m.start);
+
+    for m in overlapping {
+        if m.start > pos {
+            out.push_str(&content[pos..m.start]);
+        }
+        // Partially visible matches are masked entirely so no digits leak
+        if m.start >= start && m.end <= end {
+            out.push_str(&redact_value(&m.matched_text));
+        } else {
+            let (from, to) = (m.start.max(pos), m.end.min(end));
+            out.extend(content[from..to].chars().map(|c| if c.is_alphanumeric() { '*' } else { c }));
+        }
+        pos = pos.max(m.end.min(end));
+    }
+    if pos < end {
+        out.push_str(&content[pos..end]);
+    }
+    out
+}
+
+fn unix_now() -> u64 {
+    sase_common::Timestamp::now().as_nanos() / 1_000_000_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DLPScanner;
+
+    #[test]
+    fn test_redact_value() {
+        assert_eq!(redact_value("4111-1111-1111-1111"), "****-****-****-1111");
+        assert_eq!(redact_value("123-45-6789"), "***-**-6789");
+        assert_eq!(redact_value("abc12"), "*****");
+    }
+
+    #[test]
+    fn test_capture_redacts_context() {
+        let scanner = DLPScanner::default_classifiers();
+        let content = "ssn 123-45-6789 card 4111-1111-1111-1111 end";
+        let result = scanner.scan(content);
+
+        let store = EvidenceStore::new(EvidenceConfig::default().with_context_chars(24));
+        let source = EvidenceSource::Email { message_id: "<m1>".into(), sender: "a@b.c".into() };
+        let id = store.capture(source.clone(), content, &result).unwrap();
+
+        let incident = store.get(id).unwrap();
+        for evidence in &incident.evidence {
+            let text = format!("{}{}{}", evidence.context_before, evidence.redacted, evidence.context_after);
+            assert!(!text.contains("123-45"));
+            assert!(!text.contains("4111-1111"));
+        }
+        assert_eq!(store.for_source(&source).len(), 1);
+    }
+
+    #[test]
+    fn test_reveal_requires_second_approver() {
+        let scanner = DLPScanner::default_classifiers();
+        let content = "ssn 123-45-6789";
+        let store = EvidenceStore::new(EvidenceConfig::default().with_approver("lead"));
+        let source = EvidenceSource::File { path: "/tmp/x".into(), sha256: None };
+        let id = store.capture(source, content, &scanner.scan(content)).unwrap();
+
+        assert!(store.reveal(id, 0, "analyst").is_err());
+        let request = store.request_unmask(id, "analyst", "case 42").unwrap();
+        assert_eq!(store.approve_unmask(request, "analyst", 60), Err(EvidenceError::NotApprover("analyst".into())));
+        store.approve_unmask(request, "lead", 60).unwrap();
+
+        assert_eq!(store.reveal(id, 0, "analyst").unwrap(), "123-45-6789");
+        assert!(store.reveal(id, 0, "someone-else").is_err());
+        assert_eq!(store.audit_log().len(), 3);
+    }
+}
@@ -29,9 +29,11 @@ pub mod scanner;
 pub mod patterns;
 pub mod entropy;
 pub mod checksum;
+pub mod evidence;
 
 pub use scanner::{DLPScanner, ScanResult, Match};
 pub use patterns::PatternSet;
+pub use evidence::{EvidenceStore, EvidenceConfig, EvidenceSource, Incident};
 
 use serde::{Deserialize, Serialize};
 
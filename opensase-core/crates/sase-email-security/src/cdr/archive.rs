@@ -0,0 +1,62 @@
+//! ZIP archive rebuild
+//!
+//! Every entry is sanitised as a file of its own one level deeper, and the
+//! archive is written again from the results. Entries with unsafe paths
+//! and blocked files are left out.
+
+use super::office::{read_zip, write_zip};
+use super::{CdrEngine, CdrError, ContentKind, Disposition, RemovedContent};
+
+/// Rebuild a ZIP archive. Returns `None` when every entry came through
+/// unchanged.
+pub(super) fn rebuild_zip(
+    engine: &CdrEngine,
+    data: &[u8],
+    depth: usize,
+) -> Result<(Option<Vec<u8>>, Vec<RemovedContent>), CdrError> {
+    let entries = read_zip(data, engine.config())?;
+    let mut removed = Vec::new();
+    let mut changed = false;
+    let mut rebuilt = Vec::with_capacity(entries.len());
+
+    for (name, content) in entries {
+        if unsafe_path(&name) {
+            removed.push(RemovedContent::new(ContentKind::UnsafePath, name));
+            changed = true;
+            continue;
+        }
+        if name.ends_with('/') {
+            rebuilt.push((name, content));
+            continue;
+        }
+
+        let file = engine.sanitize_at_depth(&name, "application/octet-stream", &content, depth + 1)?;
+        match file.disposition {
+            Disposition::Clean => rebuilt.push((name, content)),
+            Disposition::Removed | Disposition::Failed => {
+                removed.push(RemovedContent::new(ContentKind::BlockedFile, name));
+                changed = true;
+            }
+            Disposition::Reconstructed | Disposition::Disarmed => {
+                removed.extend(file.removed.into_iter()
+                    .map(|r| RemovedContent::new(r.kind, format!("{}: {}", name, r.location))));
+                rebuilt.push((file.filename, file.data));
+                changed = true;
+            }
+        }
+    }
+
+    if !changed {
+        return Ok((None, removed));
+    }
+    Ok((Some(write_zip(rebuilt)?), removed))
+}
+
+/// Absolute paths, parent references and drive letters (zip slip)
+fn unsafe_path(name: &str) -> bool {
+    name.starts_with('/')
+        || name.contains('\\')
+        || name.contains('\0')
+        || name.as_bytes().get(1) == Some(&b':')
+        || name.split('/').any(|segment| segment == "..")
+}
@@ -0,0 +1,74 @@
+//! SVG and HTML script removal
+//!
+//! Markup is not re-serialised; elements and attributes that can run code
+//! or pull in remote content are cut out of the text.
+
+use super::{ContentKind, RemovedContent};
+use regex::Regex;
+use std::sync::OnceLock;
+
+struct Patterns {
+    /// (pattern, what it removes); each match is replaced with nothing
+    removals: Vec<(Regex, ContentKind)>,
+    event_handler: Regex,
+    script_url: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let element = |tag: &str, kind| {
+            // The regex crate has no backreferences, so one pattern per tag
+            let pattern = format!(r"(?is)<{tag}\b[^>]*?(?:/>|>.*?</{tag}\s*>|>)", tag = tag);
+            (Regex::new(&pattern).unwrap(), kind)
+        };
+        Patterns {
+            removals: vec![
+                element("script", ContentKind::Script),
+                element("foreignObject", ContentKind::EmbeddedObject),
+                element("iframe", ContentKind::EmbeddedObject),
+                element("frame", ContentKind::EmbeddedObject),
+                element("object", ContentKind::EmbeddedObject),
+                element("embed", ContentKind::EmbeddedObject),
+                element("applet", ContentKind::EmbeddedObject),
+                element("base", ContentKind::ExternalLink),
+                (Regex::new(r#"(?is)<meta\b[^>]*http-equiv\s*=\s*["']?refresh[^>]*>"#).unwrap(), ContentKind::ExternalLink),
+            ],
+            event_handler: Regex::new(r#"(?is)\s+on[a-z]+\s*=\s*(?:"[^"]*"|'[^']*'|[^\s>]+)"#).unwrap(),
+            script_url: Regex::new(r#"(?is)(\s(?:href|xlink:href|src|action|formaction|data)\s*=\s*["']?)\s*(?:javascript|vbscript|data\s*:\s*text/html)[^"'\s>]*"#).unwrap(),
+        }
+    })
+}
+
+/// Remove scripts, event handlers, embedded frames and script URLs. The
+/// removed list is empty when the markup was already clean.
+pub(super) fn sanitize(data: &[u8]) -> (Vec<u8>, Vec<RemovedContent>) {
+    let patterns = patterns();
+    let mut text = String::from_utf8_lossy(data).into_owned();
+    let mut removed = Vec::new();
+
+    for (pattern, kind) in &patterns.removals {
+        let count = pattern.find_iter(&text).count();
+        if count > 0 {
+            text = pattern.replace_all(&text, "").into_owned();
+            removed.push(RemovedContent::new(*kind, format!("{} element(s)", count)));
+        }
+    }
+
+    let count = patterns.event_handler.find_iter(&text).count();
+    if count > 0 {
+        text = patterns.event_handler.replace_all(&text, "").into_owned();
+        removed.push(RemovedContent::new(ContentKind::EventHandler, format!("{} attribute(s)", count)));
+    }
+
+    let count = patterns.script_url.find_iter(&text).count();
+    if count > 0 {
+        text = patterns.script_url.replace_all(&text, "${1}#").into_owned();
+        removed.push(RemovedContent::new(ContentKind::Script, format!("{} URL(s)", count)));
+    }
+
+    if removed.is_empty() {
+        return (data.to_vec(), removed);
+    }
+    (text.into_bytes(), removed)
+}
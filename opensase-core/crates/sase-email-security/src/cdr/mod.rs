@@ -0,0 +1,667 @@
+//! Content Disarm and Reconstruction
+//!
+//! Rebuilds attachments without their active content instead of trying to
+//! decide whether that content is malicious:
+//!
+//! - Office Open XML: VBA projects, ActiveX controls, embedded OLE objects,
+//!   ribbon callbacks, external links and DDE fields are removed and
+//!   macro-enabled documents become their macro-free equivalents
+//! - PDF: the file is re-serialised object by object with JavaScript,
+//!   automatic and launch actions, embedded files, XFA and rich media
+//!   disarmed
+//! - Images: decoded and re-encoded, dropping metadata and anything
+//!   appended to the pixel data; SVG and HTML lose scripts and handlers
+//! - ZIP archives: rebuilt with every entry sanitised recursively
+//!
+//! Executables and scripts are replaced by a notice. Files that cannot be
+//! rebuilt (encrypted, legacy Office with macros, unsupported archives) are
+//! removed or hold the message in quarantine, per configuration. The
+//! gateway delivers the rebuilt message as `DeliverModified` and keeps the
+//! original in quarantine.
+
+mod archive;
+mod markup;
+mod office;
+mod pdf;
+mod raster;
+
+use mail_parser::{MimeHeaders, PartType};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone)]
+pub struct CdrConfig {
+    pub enabled: bool,
+    /// Larger attachments are not rebuilt
+    pub max_file_size: usize,
+    /// Archive and attached-message nesting
+    pub max_depth: usize,
+    pub max_archive_entries: usize,
+    /// Total decompressed bytes across one file
+    pub max_expanded_size: u64,
+    /// Per-entry decompressed/compressed ratio before an archive is a bomb
+    pub max_compression_ratio: f64,
+    pub max_image_dimension: u32,
+    pub jpeg_quality: u8,
+    /// Extensions removed regardless of content
+    pub blocked_extensions: HashSet<String>,
+    pub on_failure: FailureAction,
+}
+
+impl Default for CdrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_file_size: 25 * 1024 * 1024,
+            max_depth: 3,
+            max_archive_entries: 1000,
+            max_expanded_size: 500 * 1024 * 1024,
+            max_compression_ratio: 100.0,
+            max_image_dimension: 16384,
+            jpeg_quality: 90,
+            blocked_extensions: blocked_extensions(),
+            on_failure: FailureAction::Quarantine,
+        }
+    }
+}
+
+/// Handling of attachments that cannot be rebuilt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FailureAction {
+    /// Replace the attachment with a notice and deliver
+    Remove,
+    /// Hold the whole message
+    Quarantine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileKind {
+    /// Office Open XML (docx/xlsx/pptx and macro-enabled variants)
+    Ooxml,
+    /// OLE2 compound file (doc/xls/ppt)
+    LegacyOffice,
+    Pdf,
+    Image,
+    Svg,
+    Html,
+    Zip,
+    /// RAR, 7z, gzip and other archives there is no rebuilder for
+    OtherArchive,
+    Executable,
+    Script,
+    Other,
+}
+
+/// What was done with one attachment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Disposition {
+    /// No active content; delivered as received
+    Clean,
+    /// Rebuilt; nothing active was found
+    Reconstructed,
+    /// Rebuilt with active content removed
+    Disarmed,
+    /// Replaced by a notice
+    Removed,
+    /// Could not be rebuilt
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentKind {
+    Macro,
+    ActiveX,
+    EmbeddedObject,
+    ExternalLink,
+    DdeField,
+    JavaScript,
+    /// Actions run on open, close or page events
+    AutoAction,
+    LaunchAction,
+    EmbeddedFile,
+    FormAction,
+    XfaForm,
+    RichMedia,
+    Metadata,
+    /// Bytes outside the file format's own structure (polyglots)
+    TrailingData,
+    Script,
+    EventHandler,
+    BlockedFile,
+    UnsafePath,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemovedContent {
+    pub kind: ContentKind,
+    /// Part, object or archive path it was found in
+    pub location: String,
+}
+
+impl RemovedContent {
+    fn new(kind: ContentKind, location: impl Into<String>) -> Self {
+        Self { kind, location: location.into() }
+    }
+}
+
+/// Rebuilt file
+#[derive(Debug, Clone)]
+pub struct SanitizedFile {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub kind: FileKind,
+    pub disposition: Disposition,
+    pub removed: Vec<RemovedContent>,
+}
+
+/// Per-attachment record kept with the verdict and the retained original
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CdrReport {
+    pub filename: String,
+    pub delivered_filename: Option<String>,
+    pub kind: FileKind,
+    pub disposition: Disposition,
+    pub original_size: usize,
+    pub original_sha256: String,
+    pub sanitized_size: Option<usize>,
+    pub sanitized_sha256: Option<String>,
+    pub removed: Vec<RemovedContent>,
+    pub error: Option<String>,
+}
+
+/// Result of sanitising a whole message
+#[derive(Debug, Clone)]
+pub struct CdrOutcome {
+    /// Message to deliver; the input unchanged when nothing was modified
+    pub raw: Vec<u8>,
+    pub reports: Vec<CdrReport>,
+    pub modified: bool,
+    /// An attachment failed and policy is to hold the message
+    pub quarantine: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct CdrStats {
+    pub files_processed: AtomicU64,
+    pub files_disarmed: AtomicU64,
+    pub files_reconstructed: AtomicU64,
+    pub files_removed: AtomicU64,
+    pub files_failed: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CdrStatsSnapshot {
+    pub files_processed: u64,
+    pub files_disarmed: u64,
+    pub files_reconstructed: u64,
+    pub files_removed: u64,
+    pub files_failed: u64,
+}
+
+/// CDR engine. Sanitising is CPU-bound; async callers should run it on
+/// the blocking pool.
+pub struct CdrEngine {
+    config: CdrConfig,
+    stats: CdrStats,
+}
+
+impl CdrEngine {
+    pub fn new(config: CdrConfig) -> Self {
+        Self {
+            config,
+            stats: CdrStats::default(),
+        }
+    }
+
+    pub fn config(&self) -> &CdrConfig {
+        &self.config
+    }
+
+    /// Identify a file from its content, falling back to the name
+    pub fn detect(&self, filename: &str, data: &[u8]) -> FileKind {
+        let ext = extension(filename);
+        if self.config.blocked_extensions.contains(&ext) {
+            return if SCRIPT_EXTENSIONS.contains(&ext.as_str()) { FileKind::Script } else { FileKind::Executable };
+        }
+
+        let head = &data[..data.len().min(1024)];
+        if find(head, b"%PDF-").is_some() {
+            return FileKind::Pdf;
+        }
+        if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+            return if find(data, b"[Content_Types].xml").is_some() { FileKind::Ooxml } else { FileKind::Zip };
+        }
+        if data.starts_with(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+            return FileKind::LegacyOffice;
+        }
+        if data.starts_with(&[0xFF, 0xD8, 0xFF])
+            || data.starts_with(b"\x89PNG\r\n\x1a\n")
+            || data.starts_with(b"GIF87a")
+            || data.starts_with(b"GIF89a")
+            || data.starts_with(b"BM")
+            || data.starts_with(b"II*\0")
+            || data.starts_with(b"MM\0*")
+            || (data.starts_with(b"RIFF") && data.get(8..12) == Some(&b"WEBP"[..]))
+        {
+            return FileKind::Image;
+        }
+        if data.starts_with(b"MZ")
+            || data.starts_with(b"\x7fELF")
+            || data.starts_with(&[0xCF, 0xFA, 0xED, 0xFE])
+            || data.starts_with(&[0xCE, 0xFA, 0xED, 0xFE])
+            || data.starts_with(&[0xCA, 0xFE, 0xBA, 0xBE])
+        {
+            return FileKind::Executable;
+        }
+        if data.starts_with(b"#!") {
+            return FileKind::Script;
+        }
+        if data.starts_with(b"Rar!\x1a\x07")
+            || data.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C])
+            || data.starts_with(&[0x1F, 0x8B])
+            || data.get(257..262) == Some(&b"ustar"[..])
+        {
+            return FileKind::OtherArchive;
+        }
+
+        let text = String::from_utf8_lossy(head).trim_start_matches('\u{feff}').trim_start().to_ascii_lowercase();
+        if ext == "svg" || (text.starts_with('<') && text.contains("<svg")) {
+            return FileKind::Svg;
+        }
+        if ["html", "htm", "xhtml", "shtml"].contains(&ext.as_str())
+            || text.starts_with("<!doctype html")
+            || text.starts_with("<html")
+        {
+            return FileKind::Html;
+        }
+        FileKind::Other
+    }
+
+    /// Sanitise one file
+    pub fn sanitize_file(&self, filename: &str, content_type: &str, data: &[u8]) -> Result<SanitizedFile, CdrError> {
+        let result = self.sanitize_at_depth(filename, content_type, data, 0);
+        self.count(result.as_ref().map(|f| f.disposition).unwrap_or(Disposition::Failed));
+        result
+    }
+
+    fn count(&self, disposition: Disposition) {
+        self.stats.files_processed.fetch_add(1, Ordering::Relaxed);
+        let counter = match disposition {
+            Disposition::Clean => return,
+            Disposition::Disarmed => &self.stats.files_disarmed,
+            Disposition::Reconstructed => &self.stats.files_reconstructed,
+            Disposition::Removed => &self.stats.files_removed,
+            Disposition::Failed => &self.stats.files_failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sanitize_at_depth(
+        &self,
+        filename: &str,
+        content_type: &str,
+        data: &[u8],
+        depth: usize,
+    ) -> Result<SanitizedFile, CdrError> {
+        if data.len() > self.config.max_file_size {
+            return Err(CdrError::TooLarge(data.len()));
+        }
+
+        let kind = self.detect(filename, data);
+        let unchanged = |disposition| SanitizedFile {
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            data: data.to_vec(),
+            kind,
+            disposition,
+            removed: Vec::new(),
+        };
+
+        let (data, removed, renamed, new_type) = match kind {
+            FileKind::Executable | FileKind::Script => {
+                return Ok(SanitizedFile {
+                    filename: format!("{}.removed.txt", filename),
+                    content_type: "text/plain".to_string(),
+                    data: removal_notice(filename, "executable content is not delivered").into_bytes(),
+                    kind,
+                    disposition: Disposition::Removed,
+                    removed: vec![RemovedContent::new(ContentKind::BlockedFile, filename)],
+                });
+            }
+            FileKind::Ooxml => {
+                let (rebuilt, removed) = office::disarm_ooxml(data, &self.config)?;
+                if removed.is_empty() {
+                    return Ok(unchanged(Disposition::Clean));
+                }
+                let (renamed, new_type) = office::macro_free_name(filename, content_type);
+                (rebuilt, removed, renamed, new_type)
+            }
+            FileKind::LegacyOffice => {
+                office::check_legacy(data)?;
+                return Ok(unchanged(Disposition::Clean));
+            }
+            FileKind::Pdf => {
+                let (rebuilt, removed) = pdf::rebuild(data, &self.config)?;
+                (rebuilt, removed, filename.to_string(), content_type.to_string())
+            }
+            FileKind::Image => {
+                let (rebuilt, renamed, new_type) = raster::reencode(filename, content_type, data, &self.config)?;
+                (rebuilt, Vec::new(), renamed, new_type)
+            }
+            FileKind::Svg | FileKind::Html => {
+                let (rebuilt, removed) = markup::sanitize(data);
+                if removed.is_empty() {
+                    return Ok(unchanged(Disposition::Clean));
+                }
+                (rebuilt, removed, filename.to_string(), content_type.to_string())
+            }
+            FileKind::Zip => {
+                if depth >= self.config.max_depth {
+                    return Err(CdrError::LimitExceeded(format!("archive nesting deeper than {}", self.config.max_depth)));
+                }
+                let (rebuilt, removed) = archive::rebuild_zip(self, data, depth)?;
+                if removed.is_empty() && rebuilt.is_none() {
+                    return Ok(unchanged(Disposition::Clean));
+                }
+                (rebuilt.unwrap_or_else(|| data.to_vec()), removed, filename.to_string(), content_type.to_string())
+            }
+            FileKind::OtherArchive => {
+                return Err(CdrError::Unsupported("archive format cannot be rebuilt".to_string()));
+            }
+            FileKind::Other => return Ok(unchanged(Disposition::Clean)),
+        };
+
+        Ok(SanitizedFile {
+            filename: renamed,
+            content_type: new_type,
+            data,
+            kind,
+            disposition: if removed.is_empty() { Disposition::Reconstructed } else { Disposition::Disarmed },
+            removed,
+        })
+    }
+
+    /// Sanitise every attachment of a raw RFC 5322 message, including
+    /// attached messages, and rebuild the MIME structure around them
+    pub fn sanitize_message(&self, raw: &[u8]) -> Result<CdrOutcome, CdrError> {
+        self.sanitize_mime(raw, 0)
+    }
+
+    fn sanitize_mime(&self, raw: &[u8], depth: usize) -> Result<CdrOutcome, CdrError> {
+        let message = mail_parser::MessageParser::default()
+            .parse(raw)
+            .ok_or_else(|| CdrError::Malformed("unparseable message".to_string()))?;
+
+        let mut outcome = CdrOutcome {
+            raw: Vec::new(),
+            reports: Vec::new(),
+            modified: false,
+            quarantine: false,
+        };
+        // (start, end, replacement) over `raw`
+        let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+
+        for &part_id in &message.attachments {
+            let Some(part) = message.parts.get(part_id) else { continue };
+            let filename = part.attachment_name().unwrap_or("attachment").to_string();
+            let content_type = part.content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            let data = part.contents();
+            let mut report = CdrReport {
+                filename: filename.clone(),
+                delivered_filename: None,
+                kind: FileKind::Other,
+                disposition: Disposition::Clean,
+                original_size: data.len(),
+                original_sha256: sha256_hex(data),
+                sanitized_size: None,
+                sanitized_sha256: None,
+                removed: Vec::new(),
+                error: None,
+            };
+
+            // The whole message is the attachment; there is no MIME
+            // structure to put a rebuilt part into
+            if part_id == 0 {
+                report.disposition = Disposition::Failed;
+                report.error = Some("single-part attachment message".to_string());
+                outcome.quarantine |= self.config.on_failure == FailureAction::Quarantine;
+                outcome.reports.push(report);
+                continue;
+            }
+            let span = (part.offset_header, part.offset_end);
+
+            if let PartType::Message(_) = &part.body {
+                let nested = if depth + 1 >= self.config.max_depth {
+                    Err(CdrError::LimitExceeded("attached messages nested too deeply".to_string()))
+                } else {
+                    self.sanitize_mime(data, depth + 1)
+                };
+                match nested {
+                    Ok(nested) => {
+                        report.kind = FileKind::Other;
+                        report.removed = nested.reports.iter().flat_map(|r| r.removed.clone()).collect();
+                        outcome.quarantine |= nested.quarantine;
+                        if nested.modified {
+                            report.disposition = Disposition::Disarmed;
+                            report.sanitized_size = Some(nested.raw.len());
+                            report.sanitized_sha256 = Some(sha256_hex(&nested.raw));
+                            edits.push((span.0, span.1, message_part(&nested.raw)));
+                        }
+                        outcome.reports.extend(nested.reports);
+                    }
+                    Err(e) => self.fail(&mut outcome, &mut report, &mut edits, span, &e),
+                }
+                outcome.reports.push(report);
+                continue;
+            }
+
+            match self.sanitize_at_depth(&filename, &content_type, data, depth) {
+                Ok(file) => {
+                    self.count(file.disposition);
+                    report.kind = file.kind;
+                    report.disposition = file.disposition;
+                    report.removed = file.removed.clone();
+                    match file.disposition {
+                        Disposition::Clean | Disposition::Failed => {}
+                        disposition => {
+                            report.delivered_filename = Some(file.filename.clone());
+                            report.sanitized_size = Some(file.data.len());
+                            report.sanitized_sha256 = Some(sha256_hex(&file.data));
+                            edits.push((span.0, span.1, attachment_part(&file.filename, &file.content_type, &file.data, disposition)));
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.count(Disposition::Failed);
+                    self.fail(&mut outcome, &mut report, &mut edits, span, &e);
+                }
+            }
+            outcome.reports.push(report);
+        }
+
+        if edits.is_empty() {
+            outcome.raw = raw.to_vec();
+            return Ok(outcome);
+        }
+
+        edits.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+        let mut rebuilt = raw.to_vec();
+        for (start, end, replacement) in edits {
+            if start <= end && end <= rebuilt.len() {
+                rebuilt.splice(start..end, replacement);
+            }
+        }
+        let changed = outcome.reports.iter()
+            .filter(|r| r.sanitized_size.is_some())
+            .count();
+        let mut raw = format!("X-OpenSASE-CDR: {} attachment(s) rebuilt\r\n", changed).into_bytes();
+        raw.extend_from_slice(&rebuilt);
+
+        outcome.raw = raw;
+        outcome.modified = true;
+        Ok(outcome)
+    }
+
+    fn fail(
+        &self,
+        outcome: &mut CdrOutcome,
+        report: &mut CdrReport,
+        edits: &mut Vec<(usize, usize, Vec<u8>)>,
+        span: (usize, usize),
+        error: &CdrError,
+    ) {
+        tracing::info!("CDR could not rebuild {}: {}", report.filename, error);
+        report.error = Some(error.to_string());
+        match self.config.on_failure {
+            FailureAction::Quarantine => {
+                report.disposition = Disposition::Failed;
+                outcome.quarantine = true;
+            }
+            FailureAction::Remove => {
+                report.disposition = Disposition::Removed;
+                let notice = removal_notice(&report.filename, &error.to_string());
+                let name = format!("{}.removed.txt", report.filename);
+                report.delivered_filename = Some(name.clone());
+                report.sanitized_size = Some(notice.len());
+                report.sanitized_sha256 = Some(sha256_hex(notice.as_bytes()));
+                edits.push((span.0, span.1, attachment_part(&name, "text/plain", notice.as_bytes(), Disposition::Removed)));
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CdrStatsSnapshot {
+        CdrStatsSnapshot {
+            files_processed: self.stats.files_processed.load(Ordering::Relaxed),
+            files_disarmed: self.stats.files_disarmed.load(Ordering::Relaxed),
+            files_reconstructed: self.stats.files_reconstructed.load(Ordering::Relaxed),
+            files_removed: self.stats.files_removed.load(Ordering::Relaxed),
+            files_failed: self.stats.files_failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for CdrEngine {
+    fn default() -> Self {
+        Self::new(CdrConfig::default())
+    }
+}
+
+// =============================================================================
+// MIME Helpers
+// =============================================================================
+
+fn attachment_part(filename: &str, content_type: &str, data: &[u8], disposition: Disposition) -> Vec<u8> {
+    use base64::Engine;
+
+    let mut part = format!(
+        "Content-Type: {}; {}\r\nContent-Disposition: attachment; {}\r\nContent-Transfer-Encoding: base64\r\nX-OpenSASE-CDR: {:?}\r\n\r\n",
+        content_type,
+        mime_param("name", filename),
+        mime_param("filename", filename),
+        disposition,
+    );
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    for (i, chunk) in encoded.as_bytes().chunks(76).enumerate() {
+        if i > 0 {
+            part.push_str("\r\n");
+        }
+        part.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+    }
+    part.into_bytes()
+}
+
+/// Attached messages must not be base64 encoded (RFC 2046 5.2.1)
+fn message_part(raw: &[u8]) -> Vec<u8> {
+    let mut part = b"Content-Type: message/rfc822\r\nContent-Disposition: attachment\r\nContent-Transfer-Encoding: 8bit\r\n\r\n".to_vec();
+    part.extend_from_slice(raw);
+    part
+}
+
+/// `name="value"`, or RFC 2231 encoding for non-ASCII names
+fn mime_param(name: &str, value: &str) -> String {
+    let value: String = value.chars().filter(|c| !c.is_control()).collect();
+    if value.is_ascii() {
+        format!("{}=\"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        let encoded: String = value.bytes()
+            .map(|b| if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            })
+            .collect();
+        format!("{}*=utf-8''{}", name, encoded)
+    }
+}
+
+fn removal_notice(filename: &str, reason: &str) -> String {
+    format!(
+        "The attachment \"{}\" was removed by the email security gateway: {}.\r\n\
+         Contact your administrator if you need the original file.\r\n",
+        filename, reason
+    )
+}
+
+// =============================================================================
+// Utilities
+// =============================================================================
+
+const SCRIPT_EXTENSIONS: &[&str] = &["js", "jse", "vbs", "vbe", "wsf", "wsh", "ps1", "psm1", "bat", "cmd", "sh", "bash", "hta"];
+
+fn blocked_extensions() -> HashSet<String> {
+    [
+        "exe", "com", "scr", "pif", "msi", "msp", "dll", "cpl", "sys",
+        "bat", "cmd", "ps1", "psm1", "sh", "bash",
+        "js", "jse", "vbs", "vbe", "wsf", "wsh", "hta",
+        "jar", "class", "app", "dmg", "elf", "so",
+        "lnk", "reg", "inf", "scf", "iso", "img", "vhd", "vhdx",
+    ].iter().map(|s| s.to_string()).collect()
+}
+
+pub(crate) fn extension(filename: &str) -> String {
+    match filename.rsplit_once('.') {
+        Some((_, ext)) => ext.to_ascii_lowercase(),
+        None => String::new(),
+    }
+}
+
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[derive(Debug)]
+pub enum CdrError {
+    TooLarge(usize),
+    Malformed(String),
+    /// Password-protected content cannot be inspected
+    Encrypted,
+    Unsupported(String),
+    /// Decompression or nesting limits (possible bomb)
+    LimitExceeded(String),
+}
+
+impl std::fmt::Display for CdrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge(size) => write!(f, "file too large to rebuild ({} bytes)", size),
+            Self::Malformed(e) => write!(f, "malformed file: {}", e),
+            Self::Encrypted => write!(f, "file is encrypted"),
+            Self::Unsupported(e) => write!(f, "unsupported: {}", e),
+            Self::LimitExceeded(e) => write!(f, "limit exceeded: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CdrError {}
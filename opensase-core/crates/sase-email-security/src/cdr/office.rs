@@ -0,0 +1,322 @@
+//! Office document disarm
+//!
+//! OOXML packages are ZIP files of XML parts tied together by
+//! relationship files. Active parts are dropped, relationships pointing at
+//! them or outside the package are cut, content types are switched to the
+//! macro-free variants, and DDE fields are emptied. Legacy OLE2 documents
+//! cannot be rebuilt here, so they pass only when they carry no macros or
+//! embedded objects.
+
+use super::{CdrConfig, CdrError, ContentKind, RemovedContent};
+use regex::Regex;
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use std::sync::OnceLock;
+
+/// Macro-enabled main part types and their macro-free equivalents
+const MACRO_CONTENT_TYPES: &[(&str, &str)] = &[
+    ("application/vnd.ms-word.document.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"),
+    ("application/vnd.ms-word.template.macroEnabledTemplate.main+xml",
+     "application/vnd.openxmlformats-officedocument.wordprocessingml.template.main+xml"),
+    ("application/vnd.ms-excel.sheet.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"),
+    ("application/vnd.ms-excel.template.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.spreadsheetml.template.main+xml"),
+    ("application/vnd.ms-excel.addin.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"),
+    ("application/vnd.ms-powerpoint.presentation.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml"),
+    ("application/vnd.ms-powerpoint.slideshow.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.presentationml.slideshow.main+xml"),
+    ("application/vnd.ms-powerpoint.template.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.presentationml.template.main+xml"),
+    ("application/vnd.ms-powerpoint.addin.macroEnabled.main+xml",
+     "application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml"),
+];
+
+/// Macro-enabled extensions, their replacements and MIME types
+const MACRO_EXTENSIONS: &[(&str, &str, &str)] = &[
+    ("docm", "docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("dotm", "dotx", "application/vnd.openxmlformats-officedocument.wordprocessingml.template"),
+    ("xlsm", "xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("xltm", "xltx", "application/vnd.openxmlformats-officedocument.spreadsheetml.template"),
+    ("xlam", "xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("pptm", "pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+    ("ppsm", "ppsx", "application/vnd.openxmlformats-officedocument.presentationml.slideshow"),
+    ("potm", "potx", "application/vnd.openxmlformats-officedocument.presentationml.template"),
+    ("ppam", "pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+];
+
+struct Patterns {
+    relationship: Regex,
+    attribute: Regex,
+    override_part: Regex,
+    default_type: Regex,
+    dde_instr_text: Regex,
+    dde_fld_simple: Regex,
+    attached_template: Regex,
+    dde_formula: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        relationship: Regex::new(r"(?s)<Relationship\b[^>]*?(?:/>|>.*?</Relationship>)").unwrap(),
+        attribute: Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).unwrap(),
+        override_part: Regex::new(r#"<Override\b[^>]*PartName="([^"]*)"[^>]*/>"#).unwrap(),
+        default_type: Regex::new(r#"<Default\b[^>]*ContentType="[^"]*(?:vbaProject|activeX|oleObject)[^"]*"[^>]*/>"#).unwrap(),
+        dde_instr_text: Regex::new(r"(?is)(<w:instrText\b[^>]*>)\s*DDE(?:AUTO)?\b[^<]*(</w:instrText>)").unwrap(),
+        dde_fld_simple: Regex::new(r#"(?i)(w:instr\s*=\s*")\s*DDE(?:AUTO)?\b[^"]*(")"#).unwrap(),
+        attached_template: Regex::new(r"<w:attachedTemplate\b[^>]*/>").unwrap(),
+        // cmd|'/c calc'!A0 and friends: application|topic!item
+        dde_formula: Regex::new(r"(?s)<f\b[^>]*>[^<]*\|[^<]*![^<]*</f>").unwrap(),
+    })
+}
+
+/// Part dropped from the package, by path
+fn active_part(name: &str) -> Option<ContentKind> {
+    let lower = name.to_ascii_lowercase();
+    if lower.ends_with("vbaproject.bin")
+        || lower.ends_with("vbadata.xml")
+        || lower.contains("vbaprojectsignature")
+        || lower.ends_with("attachedtoolbars.bin")
+        || lower.starts_with("customui/")
+    {
+        Some(ContentKind::Macro)
+    } else if lower.contains("/activex/") {
+        Some(ContentKind::ActiveX)
+    } else if lower.contains("/embeddings/") {
+        Some(ContentKind::EmbeddedObject)
+    } else if lower.contains("/externallinks/") || lower == "xl/connections.xml" {
+        Some(ContentKind::ExternalLink)
+    } else {
+        None
+    }
+}
+
+/// Part a `_rels/*.rels` file belongs to (`""` for the package)
+fn rels_source(name: &str) -> Option<String> {
+    let (dir, file) = name.rsplit_once("_rels/")?;
+    let source = file.strip_suffix(".rels")?;
+    Some(format!("{}{}", dir, source))
+}
+
+/// Resolve a relationship target against the directory of its source part
+fn resolve_target(source: &str, target: &str) -> String {
+    let target = target.split('#').next().unwrap_or("");
+    let mut segments: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut dir: Vec<&str> = source.split('/').collect();
+        dir.pop();
+        dir
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Rebuild an OOXML package without active content. The removed list is
+/// empty when there was nothing to remove.
+pub(super) fn disarm_ooxml(data: &[u8], config: &CdrConfig) -> Result<(Vec<u8>, Vec<RemovedContent>), CdrError> {
+    let parts = read_zip(data, config)?;
+    let patterns = patterns();
+    let mut removed = Vec::new();
+
+    let mut dropped: HashSet<String> = HashSet::new();
+    for (name, _) in &parts {
+        if let Some(kind) = active_part(name) {
+            dropped.insert(name.clone());
+            removed.push(RemovedContent::new(kind, name.clone()));
+        }
+    }
+    // Relationship files of dropped parts go with them
+    for (name, _) in &parts {
+        if rels_source(name).map(|source| dropped.contains(&source)).unwrap_or(false) {
+            dropped.insert(name.clone());
+        }
+    }
+
+    let mut rebuilt = Vec::with_capacity(parts.len());
+    for (name, content) in parts {
+        if dropped.contains(&name) {
+            continue;
+        }
+
+        let content = if let Some(source) = rels_source(&name) {
+            let xml = String::from_utf8_lossy(&content).into_owned();
+            let xml = patterns.relationship.replace_all(&xml, |caps: &regex::Captures| {
+                let element = &caps[0];
+                let attrs: Vec<(String, String)> = patterns.attribute.captures_iter(element)
+                    .map(|a| (a[1].to_string(), a[2].to_string()))
+                    .collect();
+                let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+                let target = attr("Target").unwrap_or("");
+                let external = attr("TargetMode").map(|m| m.eq_ignore_ascii_case("External")).unwrap_or(false);
+                let hyperlink = attr("Type").map(|t| t.ends_with("/hyperlink")).unwrap_or(false);
+
+                if external && !hyperlink {
+                    removed.push(RemovedContent::new(ContentKind::ExternalLink, format!("{} -> {}", name, target)));
+                    String::new()
+                } else if !external && dropped.contains(&resolve_target(&source, target)) {
+                    String::new()
+                } else {
+                    element.to_string()
+                }
+            });
+            xml.into_owned().into_bytes()
+        } else if name == "[Content_Types].xml" {
+            let xml = String::from_utf8_lossy(&content).into_owned();
+            let xml = patterns.override_part.replace_all(&xml, |caps: &regex::Captures| {
+                if dropped.contains(caps[1].trim_start_matches('/')) {
+                    String::new()
+                } else {
+                    caps[0].to_string()
+                }
+            });
+            let mut xml = patterns.default_type.replace_all(&xml, "").into_owned();
+            for (macro_type, plain_type) in MACRO_CONTENT_TYPES {
+                if xml.contains(macro_type) {
+                    xml = xml.replace(macro_type, plain_type);
+                    removed.push(RemovedContent::new(ContentKind::Macro, "[Content_Types].xml"));
+                }
+            }
+            xml.into_bytes()
+        } else if name.starts_with("word/") && name.ends_with(".xml") {
+            let xml = String::from_utf8_lossy(&content).into_owned();
+            let mut changed = false;
+            let mut xml = xml;
+            for pattern in [&patterns.dde_instr_text, &patterns.dde_fld_simple] {
+                if pattern.is_match(&xml) {
+                    xml = pattern.replace_all(&xml, "${1}${2}").into_owned();
+                    removed.push(RemovedContent::new(ContentKind::DdeField, name.clone()));
+                    changed = true;
+                }
+            }
+            if patterns.attached_template.is_match(&xml) {
+                xml = patterns.attached_template.replace_all(&xml, "").into_owned();
+                changed = true;
+            }
+            if changed { xml.into_bytes() } else { content }
+        } else if name.starts_with("xl/worksheets/") && name.ends_with(".xml") {
+            let xml = String::from_utf8_lossy(&content).into_owned();
+            if patterns.dde_formula.is_match(&xml) {
+                removed.push(RemovedContent::new(ContentKind::DdeField, name.clone()));
+                // The cached <v> value stays, so the cell still shows its last result
+                patterns.dde_formula.replace_all(&xml, "").into_owned().into_bytes()
+            } else {
+                content
+            }
+        } else {
+            content
+        };
+        rebuilt.push((name, content));
+    }
+
+    if removed.is_empty() {
+        return Ok((Vec::new(), removed));
+    }
+    Ok((write_zip(rebuilt)?, removed))
+}
+
+/// Filename and MIME type after macro removal
+pub(super) fn macro_free_name(filename: &str, content_type: &str) -> (String, String) {
+    let ext = super::extension(filename);
+    match MACRO_EXTENSIONS.iter().find(|(from, _, _)| *from == ext) {
+        Some((_, to, mime)) => {
+            let stem = &filename[..filename.len() - ext.len()];
+            (format!("{}{}", stem, to), mime.to_string())
+        }
+        None => (filename.to_string(), content_type.to_string()),
+    }
+}
+
+/// OLE2 documents pass only without macros or embedded objects
+pub(super) fn check_legacy(data: &[u8]) -> Result<(), CdrError> {
+    let utf16 = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect() };
+    for marker in ["_VBA_PROJECT", "Macros", "VBA"] {
+        if super::find(data, &utf16(marker)).is_some() {
+            return Err(CdrError::Unsupported("legacy Office document with macros cannot be rebuilt".to_string()));
+        }
+    }
+    for marker in ["ObjectPool", "\u{1}Ole10Native", "MBD"] {
+        if super::find(data, &utf16(marker)).is_some() {
+            return Err(CdrError::Unsupported("legacy Office document with embedded objects cannot be rebuilt".to_string()));
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// ZIP Helpers
+// =============================================================================
+
+/// Read every entry, enforcing entry count, ratio and total size limits
+pub(super) fn read_zip(data: &[u8], config: &CdrConfig) -> Result<Vec<(String, Vec<u8>)>, CdrError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|e| CdrError::Malformed(e.to_string()))?;
+    if archive.len() > config.max_archive_entries {
+        return Err(CdrError::LimitExceeded(format!("{} archive entries", archive.len())));
+    }
+
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut total: u64 = 0;
+    for i in 0..archive.len() {
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::UnsupportedArchive(msg)) if msg.contains("Password") => {
+                return Err(CdrError::Encrypted);
+            }
+            Err(e) => return Err(CdrError::Malformed(e.to_string())),
+        };
+        let name = entry.name().to_string();
+        if entry.is_dir() {
+            entries.push((name, Vec::new()));
+            continue;
+        }
+
+        let ratio = entry.size() as f64 / entry.compressed_size().max(1) as f64;
+        if ratio > config.max_compression_ratio && entry.size() > 1024 * 1024 {
+            return Err(CdrError::LimitExceeded(format!("{} expands {:.0}x", name, ratio)));
+        }
+        // Declared sizes can lie; bound the actual read
+        let remaining = config.max_expanded_size.saturating_sub(total);
+        let mut content = Vec::new();
+        (&mut entry).take(remaining.saturating_add(1)).read_to_end(&mut content)
+            .map_err(|e| CdrError::Malformed(format!("{}: {}", name, e)))?;
+        total += content.len() as u64;
+        if total > config.max_expanded_size {
+            return Err(CdrError::LimitExceeded(format!("more than {} bytes expanded", config.max_expanded_size)));
+        }
+        entries.push((name, content));
+    }
+    Ok(entries)
+}
+
+pub(super) fn write_zip(entries: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, CdrError> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, content) in entries {
+        if name.ends_with('/') {
+            writer.add_directory(name, options)
+                .map_err(|e| CdrError::Malformed(e.to_string()))?;
+            continue;
+        }
+        writer.start_file(name, options)
+            .map_err(|e| CdrError::Malformed(e.to_string()))?;
+        writer.write_all(&content)
+            .map_err(|e| CdrError::Malformed(e.to_string()))?;
+    }
+    let cursor = writer.finish().map_err(|e| CdrError::Malformed(e.to_string()))?;
+    Ok(cursor.into_inner())
+}
@@ -0,0 +1,535 @@
+//! PDF rebuild
+//!
+//! The file is read object by object instead of through its
+//! cross-reference table, so bytes before the header or after the end of
+//! the document and objects reachable only through a forged xref are not
+//! carried over. Object streams are expanded, dangerous names are renamed
+//! out of the PDF vocabulary (`/JavaScript` becomes `/CDRJavaScript`,
+//! which readers ignore), embedded files and XMP metadata are emptied, and
+//! a fresh file with a new xref table and trailer is written.
+
+use super::{find, CdrConfig, CdrError, ContentKind, RemovedContent};
+use flate2::read::ZlibDecoder;
+use regex::bytes::{NoExpand, Regex};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::sync::OnceLock;
+
+/// Highest object number written; the xref table has one row per number
+const MAX_OBJECTS: u32 = 1_000_000;
+
+/// Names whose dictionaries or actions are disarmed
+const DANGEROUS_NAMES: &[(&str, ContentKind)] = &[
+    ("JavaScript", ContentKind::JavaScript),
+    ("JS", ContentKind::JavaScript),
+    ("OpenAction", ContentKind::AutoAction),
+    ("AA", ContentKind::AutoAction),
+    ("Launch", ContentKind::LaunchAction),
+    ("EmbeddedFiles", ContentKind::EmbeddedFile),
+    ("EmbeddedFile", ContentKind::EmbeddedFile),
+    ("FileAttachment", ContentKind::EmbeddedFile),
+    ("SubmitForm", ContentKind::FormAction),
+    ("ImportData", ContentKind::FormAction),
+    ("XFA", ContentKind::XfaForm),
+    ("RichMedia", ContentKind::RichMedia),
+    ("Movie", ContentKind::RichMedia),
+    ("Sound", ContentKind::RichMedia),
+    ("Rendition", ContentKind::RichMedia),
+    ("GoToR", ContentKind::ExternalLink),
+    ("GoToE", ContentKind::ExternalLink),
+];
+
+struct Patterns {
+    header: Regex,
+    object: Regex,
+    integer_object: Regex,
+    length: Regex,
+    type_name: Regex,
+    filter: Regex,
+    count: Regex,
+    first: Regex,
+    root: Regex,
+    encrypt: Regex,
+    info: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        header: Regex::new(r"(?-u)%PDF-(\d\.\d)").unwrap(),
+        object: Regex::new(r"(?-u)(\d+)\s+(\d+)\s+obj\b").unwrap(),
+        integer_object: Regex::new(r"(?-u)(\d+)\s+\d+\s+obj\s*(\d+)\s*endobj").unwrap(),
+        length: Regex::new(r"(?-u)/Length\s+(\d+)(?:\s+(\d+)\s+R)?").unwrap(),
+        type_name: Regex::new(r"(?-u)/Type\s*/([^\s/<>\[\]()%]+)").unwrap(),
+        filter: Regex::new(r"(?-u)/Filter\s*(\[)?\s*/([^\s/<>\[\]()%]+)").unwrap(),
+        count: Regex::new(r"(?-u)/N\s+(\d+)").unwrap(),
+        first: Regex::new(r"(?-u)/First\s+(\d+)").unwrap(),
+        root: Regex::new(r"(?-u)/Root\s+(\d+)\s+(\d+)\s+R").unwrap(),
+        encrypt: Regex::new(r"(?-u)/Encrypt\b").unwrap(),
+        info: Regex::new(r"(?-u)/Info\s+\d+\s+\d+\s+R").unwrap(),
+    })
+}
+
+struct Object {
+    generation: u32,
+    body: Vec<u8>,
+    stream: Option<Vec<u8>>,
+}
+
+/// Rebuild a PDF. Every PDF is rewritten; the removed list says whether
+/// anything active was found.
+pub(super) fn rebuild(data: &[u8], config: &CdrConfig) -> Result<(Vec<u8>, Vec<RemovedContent>), CdrError> {
+    let patterns = patterns();
+    let mut removed = Vec::new();
+
+    let header = patterns.header.captures(data)
+        .ok_or_else(|| CdrError::Malformed("no PDF header".to_string()))?;
+    let header_start = header.get(0).map(|m| m.start()).unwrap_or(0);
+    let version = String::from_utf8_lossy(&header[1]).into_owned();
+    if header_start > 0 {
+        record(&mut removed, ContentKind::TrailingData, "before header");
+    }
+    if let Some(eof) = data.windows(5).rposition(|w| w == b"%%EOF") {
+        if !trim(&data[eof + 5..]).is_empty() {
+            record(&mut removed, ContentKind::TrailingData, "after %%EOF");
+        }
+    }
+
+    // Stream lengths are often indirect: `/Length 12 0 R`
+    let integers: HashMap<u32, usize> = patterns.integer_object.captures_iter(data)
+        .filter_map(|c| Some((parse(&c[1])?, parse(&c[2])?)))
+        .collect();
+
+    // Scan objects in file order; later definitions (incremental updates)
+    // replace earlier ones
+    let mut objects: BTreeMap<u32, Object> = BTreeMap::new();
+    let mut object_streams: Vec<Object> = Vec::new();
+    // (position, dictionary) of trailers and xref streams
+    let mut trailers: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut gaps: Vec<(usize, usize)> = Vec::new();
+    let mut pos = header_start;
+
+    while let Some(caps) = patterns.object.captures(&data[pos..]) {
+        let matched = caps.get(0).map(|m| (pos + m.start(), pos + m.end())).unwrap_or((pos, pos));
+        gaps.push((pos, matched.0));
+        let (Some(number), Some(generation)) = (parse(&caps[1]), parse::<u32>(&caps[2])) else {
+            pos = matched.1;
+            continue;
+        };
+
+        let body_end = value_end(data, matched.1);
+        let body = trim(&data[matched.1..body_end]).to_vec();
+        let mut next = skip_whitespace(data, body_end);
+
+        let mut stream = None;
+        if data[next..].starts_with(b"stream") {
+            let mut start = next + 6;
+            if data.get(start) == Some(&b'\r') {
+                start += 1;
+            }
+            if data.get(start) == Some(&b'\n') {
+                start += 1;
+            }
+            let declared = patterns.length.captures(&body).and_then(|c| match c.get(2) {
+                Some(_) => integers.get(&parse(&c[1])?).copied(),
+                None => parse(&c[1]),
+            });
+            let end = declared
+                .filter(|len| start + len <= data.len())
+                .filter(|len| data[skip_whitespace(data, start + len)..].starts_with(b"endstream"))
+                .map(|len| start + len);
+            let end = match end {
+                Some(end) => end,
+                // Wrong or missing length: fall back to the keyword
+                None => {
+                    let keyword = find(&data[start..], b"endstream")
+                        .map(|p| start + p)
+                        .ok_or_else(|| CdrError::Malformed(format!("object {} has an unterminated stream", number)))?;
+                    let mut end = keyword;
+                    if end > start && data[end - 1] == b'\n' {
+                        end -= 1;
+                    }
+                    if end > start && data[end - 1] == b'\r' {
+                        end -= 1;
+                    }
+                    end
+                }
+            };
+            stream = Some(data[start..end].to_vec());
+            next = skip_whitespace(data, end);
+            if data[next..].starts_with(b"endstream") {
+                next = skip_whitespace(data, next + 9);
+            }
+        }
+        if data[next..].starts_with(b"endobj") {
+            next += 6;
+        }
+        pos = next.max(matched.1);
+
+        if number == 0 {
+            continue;
+        }
+        if number >= MAX_OBJECTS {
+            return Err(CdrError::LimitExceeded(format!("object number {}", number)));
+        }
+        let object = Object { generation, body, stream };
+        match type_of(&object.body).as_deref() {
+            Some("XRef") => trailers.push((matched.0, object.body)),
+            Some("ObjStm") => object_streams.push(object),
+            _ => {
+                objects.insert(number, object);
+            }
+        }
+    }
+    gaps.push((pos, data.len()));
+
+    for (start, end) in gaps {
+        let mut from = start;
+        while let Some(p) = find(&data[from..end], b"trailer") {
+            let dict_start = from + p + 7;
+            let dict_end = value_end(data, dict_start).max(dict_start);
+            trailers.push((dict_start, data[dict_start..dict_end].to_vec()));
+            from = dict_start;
+        }
+    }
+    trailers.sort_by_key(|(position, _)| *position);
+
+    if trailers.iter().any(|(_, dict)| patterns.encrypt.is_match(dict)) {
+        return Err(CdrError::Encrypted);
+    }
+    if trailers.iter().any(|(_, dict)| patterns.info.is_match(dict)) {
+        record(&mut removed, ContentKind::Metadata, "document information");
+    }
+
+    // Objects defined directly take precedence over compressed ones
+    let mut expanded: u64 = 0;
+    for stream in &object_streams {
+        for (number, body) in expand_object_stream(stream, config, &mut expanded)? {
+            if number != 0 && number < MAX_OBJECTS {
+                objects.entry(number).or_insert(Object { generation: 0, body, stream: None });
+            }
+        }
+    }
+
+    let root = trailers.iter().rev()
+        .find_map(|(_, dict)| {
+            let c = patterns.root.captures(dict)?;
+            Some((parse::<u32>(&c[1])?, parse::<u32>(&c[2])?))
+        })
+        .filter(|(number, _)| objects.contains_key(number))
+        .or_else(|| objects.iter()
+            .find(|(_, o)| type_of(&o.body).as_deref() == Some("Catalog"))
+            .map(|(number, o)| (*number, o.generation)))
+        .ok_or_else(|| CdrError::Malformed("no document catalog".to_string()))?;
+
+    for (number, object) in objects.iter_mut() {
+        let location = format!("object {} {}", number, object.generation);
+        object.body = sanitize_names(&object.body, &location, &mut removed);
+
+        match type_of(&object.body).as_deref() {
+            Some("Metadata") => {
+                record(&mut removed, ContentKind::Metadata, location);
+                object.body = b"null".to_vec();
+                object.stream = None;
+            }
+            // Renamed above, which also recorded it
+            Some("CDREmbeddedFile") => {
+                object.body = b"null".to_vec();
+                object.stream = None;
+            }
+            _ => {}
+        }
+        if let Some(stream) = &object.stream {
+            object.body = set_length(&object.body, stream.len());
+        }
+    }
+
+    Ok((write(&version, &objects, root), removed))
+}
+
+/// Objects held in a `/Type /ObjStm` stream as (number, body)
+fn expand_object_stream(stream: &Object, config: &CdrConfig, expanded: &mut u64) -> Result<Vec<(u32, Vec<u8>)>, CdrError> {
+    let patterns = patterns();
+    let raw = stream.stream.as_deref().unwrap_or_default();
+
+    let decoded = match patterns.filter.captures(&stream.body) {
+        None => raw.to_vec(),
+        Some(c) if &c[2] == b"FlateDecode" && (c.get(1).is_none() || trim_array_single(&stream.body)) => {
+            let remaining = config.max_expanded_size.saturating_sub(*expanded);
+            let mut decoded = Vec::new();
+            ZlibDecoder::new(raw).take(remaining.saturating_add(1)).read_to_end(&mut decoded)
+                .map_err(|e| CdrError::Malformed(format!("object stream: {}", e)))?;
+            decoded
+        }
+        Some(c) => {
+            return Err(CdrError::Unsupported(format!("object stream filter {}", String::from_utf8_lossy(&c[2]))));
+        }
+    };
+    *expanded += decoded.len() as u64;
+    if *expanded > config.max_expanded_size {
+        return Err(CdrError::LimitExceeded(format!("more than {} bytes expanded", config.max_expanded_size)));
+    }
+
+    let count: usize = patterns.count.captures(&stream.body).and_then(|c| parse(&c[1])).unwrap_or(0);
+    let first: usize = patterns.first.captures(&stream.body).and_then(|c| parse(&c[1])).unwrap_or(0);
+    if first > decoded.len() {
+        return Err(CdrError::Malformed("object stream offset past its end".to_string()));
+    }
+
+    let numbers: Vec<usize> = decoded[..first]
+        .split(|b| b.is_ascii_whitespace())
+        .filter_map(parse)
+        .collect();
+    let pairs: Vec<(usize, usize)> = numbers.chunks_exact(2).take(count).map(|p| (p[0], p[1])).collect();
+
+    let mut bodies = Vec::with_capacity(pairs.len());
+    for (i, &(number, offset)) in pairs.iter().enumerate() {
+        let start = first + offset;
+        let end = pairs.get(i + 1).map(|&(_, next)| first + next).unwrap_or(decoded.len());
+        if start <= end && end <= decoded.len() {
+            bodies.push((number as u32, trim(&decoded[start..end]).to_vec()));
+        }
+    }
+    Ok(bodies)
+}
+
+/// `/Filter [/FlateDecode]` with a single entry
+fn trim_array_single(dict: &[u8]) -> bool {
+    let Some(p) = find(dict, b"/Filter") else { return false };
+    let rest = &dict[p..];
+    match (find(rest, b"["), find(rest, b"]")) {
+        (Some(open), Some(close)) if open < close => rest[open + 1..close].iter().filter(|&&b| b == b'/').count() == 1,
+        _ => false,
+    }
+}
+
+fn write(version: &str, objects: &BTreeMap<u32, Object>, root: (u32, u32)) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(format!("%PDF-{}\n", version).as_bytes());
+    out.extend_from_slice(b"%\xE2\xE3\xCF\xD3\n");
+
+    let size = objects.keys().next_back().map(|n| n + 1).unwrap_or(1);
+    let mut offsets: HashMap<u32, (usize, u32)> = HashMap::with_capacity(objects.len());
+    for (number, object) in objects {
+        offsets.insert(*number, (out.len(), object.generation));
+        out.extend_from_slice(format!("{} {} obj\n", number, object.generation).as_bytes());
+        out.extend_from_slice(&object.body);
+        if let Some(stream) = &object.stream {
+            out.extend_from_slice(b"\nstream\r\n");
+            out.extend_from_slice(stream);
+            out.extend_from_slice(b"\r\nendstream");
+        }
+        out.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n", size).as_bytes());
+    out.extend_from_slice(b"0000000000 65535 f\r\n");
+    for number in 1..size {
+        match offsets.get(&number) {
+            Some((offset, generation)) => out.extend_from_slice(format!("{:010} {:05} n\r\n", offset, generation).as_bytes()),
+            None => out.extend_from_slice(b"0000000000 00000 f\r\n"),
+        }
+    }
+    out.extend_from_slice(format!(
+        "trailer\n<< /Size {} /Root {} {} R >>\nstartxref\n{}\n%%EOF\n",
+        size, root.0, root.1, xref
+    ).as_bytes());
+    out
+}
+
+// =============================================================================
+// Lexing
+// =============================================================================
+
+/// Rename dangerous names and normalise `#xx` escapes so that
+/// `/J#61vaScript` cannot slip past. Comments are dropped.
+fn sanitize_names(body: &[u8], location: &str, removed: &mut Vec<RemovedContent>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            b'(' => {
+                let end = string_end(body, i);
+                out.extend_from_slice(&body[i..end]);
+                i = end;
+            }
+            b'<' if body.get(i + 1) == Some(&b'<') => {
+                out.extend_from_slice(b"<<");
+                i += 2;
+            }
+            b'<' => {
+                let end = body[i..].iter().position(|&b| b == b'>').map(|p| i + p + 1).unwrap_or(body.len());
+                out.extend_from_slice(&body[i..end]);
+                i = end;
+            }
+            b'%' => {
+                while i < body.len() && body[i] != b'\n' && body[i] != b'\r' {
+                    i += 1;
+                }
+                out.push(b'\n');
+            }
+            b'/' => {
+                let mut name = Vec::new();
+                let mut j = i + 1;
+                while j < body.len() && is_regular(body[j]) {
+                    if body[j] == b'#' {
+                        if let Some(byte) = body.get(j + 1..j + 3)
+                            .and_then(|hex| std::str::from_utf8(hex).ok())
+                            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        {
+                            name.push(byte);
+                            j += 3;
+                            continue;
+                        }
+                    }
+                    name.push(body[j]);
+                    j += 1;
+                }
+
+                out.push(b'/');
+                if let Some((_, kind)) = DANGEROUS_NAMES.iter().find(|(n, _)| n.as_bytes() == name.as_slice()) {
+                    record(removed, *kind, location);
+                    out.extend_from_slice(b"CDR");
+                }
+                for &byte in &name {
+                    if is_regular(byte) && byte != b'#' && (0x21..=0x7E).contains(&byte) {
+                        out.push(byte);
+                    } else {
+                        out.extend_from_slice(format!("#{:02X}", byte).as_bytes());
+                    }
+                }
+                i = j;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// End of the value at `start`: after the closing `>>` or `]` of a
+/// container, otherwise at the next `stream` or `endobj` keyword
+fn value_end(data: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < data.len() {
+        match data[i] {
+            b'(' => {
+                i = string_end(data, i);
+                continue;
+            }
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'<' if data.get(i + 1) == Some(&b'<') => {
+                depth += 1;
+                i += 2;
+                continue;
+            }
+            b'<' => {
+                i = data[i..].iter().position(|&b| b == b'>').map(|p| i + p + 1).unwrap_or(data.len());
+                continue;
+            }
+            b'>' if data.get(i + 1) == Some(&b'>') => {
+                depth = depth.saturating_sub(1);
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+                continue;
+            }
+            b'[' => depth += 1,
+            b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ if depth == 0
+                && (i == 0 || !is_regular(data[i - 1]))
+                && (data[i..].starts_with(b"stream") || data[i..].starts_with(b"endobj")) =>
+            {
+                return i;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    data.len()
+}
+
+/// Position after the literal string opening at `start`
+fn string_end(data: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < data.len() {
+        match data[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    data.len()
+}
+
+fn set_length(dict: &[u8], length: usize) -> Vec<u8> {
+    let entry = format!("/Length {}", length);
+    if patterns().length.is_match(dict) {
+        patterns().length.replacen(dict, 1, NoExpand(entry.as_bytes())).into_owned()
+    } else if let Some(rest) = dict.strip_prefix(b"<<") {
+        let mut out = format!("<< {}", entry).into_bytes();
+        out.extend_from_slice(rest);
+        out
+    } else {
+        dict.to_vec()
+    }
+}
+
+fn type_of(body: &[u8]) -> Option<String> {
+    patterns().type_name.captures(body).map(|c| String::from_utf8_lossy(&c[1]).into_owned())
+}
+
+fn record(removed: &mut Vec<RemovedContent>, kind: ContentKind, location: impl Into<String>) {
+    let item = RemovedContent::new(kind, location);
+    if !removed.contains(&item) {
+        removed.push(item);
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !b"()<>[]{}/%".contains(&b)
+}
+
+fn skip_whitespace(data: &[u8], mut i: usize) -> usize {
+    while i < data.len() && is_whitespace(data[i]) {
+        i += 1;
+    }
+    i
+}
+
+fn trim(data: &[u8]) -> &[u8] {
+    let start = skip_whitespace(data, 0);
+    let end = data.iter().rposition(|&b| !is_whitespace(b)).map(|p| p + 1).unwrap_or(start);
+    &data[start..end.max(start)]
+}
+
+fn parse<T: std::str::FromStr>(digits: &[u8]) -> Option<T> {
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
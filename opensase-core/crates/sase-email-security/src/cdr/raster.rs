@@ -0,0 +1,67 @@
+//! Image re-encoding
+//!
+//! Images are decoded to pixels and encoded again, which drops EXIF/XMP
+//! metadata, ICC profiles, comments and anything appended after the image
+//! data. JPEG, PNG and GIF keep their format (GIF keeps the first frame
+//! only); BMP, TIFF and WebP are converted to PNG.
+
+use super::{CdrConfig, CdrError};
+use image::{DynamicImage, ImageError, ImageFormat, ImageOutputFormat};
+use std::io::Cursor;
+
+/// Re-encode an image, returning the data and the possibly changed
+/// filename and content type
+pub(super) fn reencode(
+    filename: &str,
+    content_type: &str,
+    data: &[u8],
+    config: &CdrConfig,
+) -> Result<(Vec<u8>, String, String), CdrError> {
+    let mut reader = image::io::Reader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| CdrError::Malformed(e.to_string()))?;
+    let format = reader.format()
+        .ok_or_else(|| CdrError::Unsupported("unknown image format".to_string()))?;
+
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(config.max_image_dimension);
+    limits.max_image_height = Some(config.max_image_dimension);
+    limits.max_alloc = Some(config.max_expanded_size);
+    reader.limits(limits);
+
+    let decoded = reader.decode().map_err(|e| match e {
+        ImageError::Limits(e) => CdrError::LimitExceeded(e.to_string()),
+        ImageError::Unsupported(e) => CdrError::Unsupported(e.to_string()),
+        e => CdrError::Malformed(e.to_string()),
+    })?;
+
+    let (output, extension, mime) = match format {
+        ImageFormat::Jpeg => (ImageOutputFormat::Jpeg(config.jpeg_quality), None, "image/jpeg"),
+        ImageFormat::Png => (ImageOutputFormat::Png, None, "image/png"),
+        ImageFormat::Gif => (ImageOutputFormat::Gif, None, "image/gif"),
+        _ => (ImageOutputFormat::Png, Some("png"), "image/png"),
+    };
+    // JPEG has no alpha channel
+    let decoded = match output {
+        ImageOutputFormat::Jpeg(_) => DynamicImage::ImageRgb8(decoded.to_rgb8()),
+        _ => decoded,
+    };
+
+    let mut encoded = Cursor::new(Vec::with_capacity(data.len()));
+    decoded.write_to(&mut encoded, output)
+        .map_err(|e| CdrError::Malformed(format!("re-encoding failed: {}", e)))?;
+
+    let filename = match extension {
+        Some(extension) => match filename.rsplit_once('.') {
+            Some((stem, _)) => format!("{}.{}", stem, extension),
+            None => format!("{}.{}", filename, extension),
+        },
+        None => filename.to_string(),
+    };
+    let content_type = if extension.is_some() || !content_type.starts_with("image/") {
+        mime.to_string()
+    } else {
+        content_type.to_string()
+    };
+    Ok((encoded.into_inner(), filename, content_type))
+}
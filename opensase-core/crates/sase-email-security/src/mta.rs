@@ -13,6 +13,8 @@ pub struct MilterHandler {
     connections: dashmap::DashMap<u64, ConnectionState>,
     /// Next connection ID
     next_conn_id: std::sync::atomic::AtomicU64,
+    /// Messages rebuilt by CDR, waiting to replace the original
+    replacements: dashmap::DashMap<u64, Vec<u8>>,
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +51,7 @@ impl MilterHandler {
             gateway,
             connections: dashmap::DashMap::new(),
             next_conn_id: std::sync::atomic::AtomicU64::new(1),
+            replacements: dashmap::DashMap::new(),
         }
     }
     
@@ -128,7 +131,11 @@ impl MilterHandler {
         };
         
         // Process through gateway
-        let verdict = self.gateway.process(&message).await;
+        let processed = self.gateway.process_raw(raw_message, &message).await;
+        let verdict = processed.verdict;
+        if let Some(rebuilt) = processed.delivered {
+            self.replacements.insert(conn_id, rebuilt);
+        }
         
         // Convert verdict to milter response
         let response = match verdict.action {
@@ -143,14 +150,22 @@ impl MilterHandler {
         (response, Some(verdict))
     }
     
+    /// Message to hand back to the MTA in place of the one passed to
+    /// `on_eom`, when CDR rebuilt its attachments
+    pub fn take_replacement(&self, conn_id: u64) -> Option<Vec<u8>> {
+        self.replacements.remove(&conn_id).map(|(_, raw)| raw)
+    }
+    
     /// Handle connection close
     pub fn on_close(&self, conn_id: u64) {
         self.connections.remove(&conn_id);
+        self.replacements.remove(&conn_id);
     }
     
     /// Handle abort
     pub fn on_abort(&self, conn_id: u64) {
         // Reset message state but keep connection
+        self.replacements.remove(&conn_id);
         if let Some(mut conn) = self.connections.get_mut(&conn_id) {
             conn.mail_from.clear();
             conn.rcpt_to.clear();
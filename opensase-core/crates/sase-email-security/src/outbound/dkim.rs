@@ -0,0 +1,700 @@
+//! DKIM Signing
+//!
+//! RFC 6376 signatures for mail relayed outbound, with RSA-SHA256 and
+//! Ed25519-SHA256 (RFC 8463) keys held per signing domain. A rotation stages
+//! the next selector ahead of time so its DNS record can propagate before it
+//! signs anything; the outgoing selector is kept as retiring until messages
+//! it signed have had time to be verified, then reported for DNS removal.
+
+use crate::auth::{Canonicalization, CanonicalizationType, DkimAlgorithm};
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Header fields signed when present
+const DEFAULT_SIGNED_HEADERS: &[&str] = &[
+    "from", "reply-to", "subject", "date", "to", "cc", "message-id",
+    "in-reply-to", "references", "mime-version", "content-type",
+    "content-transfer-encoding",
+];
+
+/// Header fields signed once more than they occur so none can be added
+/// after signing without breaking the signature
+const DEFAULT_OVERSIGNED_HEADERS: &[&str] = &["from", "reply-to", "subject"];
+
+// =============================================================================
+// Keys
+// =============================================================================
+
+/// Private signing key
+pub struct DkimPrivateKey {
+    material: KeyMaterial,
+}
+
+enum KeyMaterial {
+    Rsa(RsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+impl DkimPrivateKey {
+    /// Load a PEM key: PKCS#8 (`PRIVATE KEY`, RSA or Ed25519) or PKCS#1
+    /// (`RSA PRIVATE KEY`). RSA keys must be 2048 to 4096 bits.
+    pub fn from_pem(pem: &str) -> Result<Self, DkimError> {
+        let (label, der) = decode_pem(pem)?;
+        match label.as_str() {
+            "PRIVATE KEY" => Self::from_pkcs8_der(&der),
+            "RSA PRIVATE KEY" => Self::from_rsa_der(&der),
+            other => Err(DkimError::InvalidKey(format!("unsupported PEM block {}", other))),
+        }
+    }
+
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<Self, DkimError> {
+        // PKCS#8 carries its own algorithm identifier; try Ed25519 first as
+        // it is the cheaper parse
+        if let Ok(pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+            return Ok(Self { material: KeyMaterial::Ed25519(pair) });
+        }
+        RsaKeyPair::from_pkcs8(der)
+            .map(|pair| Self { material: KeyMaterial::Rsa(pair) })
+            .map_err(|e| DkimError::InvalidKey(e.to_string()))
+    }
+
+    /// PKCS#1 RSAPrivateKey
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, DkimError> {
+        RsaKeyPair::from_der(der)
+            .map(|pair| Self { material: KeyMaterial::Rsa(pair) })
+            .map_err(|e| DkimError::InvalidKey(e.to_string()))
+    }
+
+    pub fn algorithm(&self) -> DkimAlgorithm {
+        match self.material {
+            KeyMaterial::Rsa(_) => DkimAlgorithm::RsaSha256,
+            KeyMaterial::Ed25519(_) => DkimAlgorithm::Ed25519Sha256,
+        }
+    }
+
+    /// TXT record to publish at `<selector>._domainkey.<domain>`
+    pub fn dns_record(&self) -> String {
+        match &self.material {
+            KeyMaterial::Rsa(pair) => format!(
+                "v=DKIM1; k=rsa; p={}",
+                b64.encode(rsa_spki(pair.public_key().as_ref()))
+            ),
+            KeyMaterial::Ed25519(pair) => format!(
+                "v=DKIM1; k=ed25519; p={}",
+                b64.encode(pair.public_key().as_ref())
+            ),
+        }
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DkimError> {
+        match &self.material {
+            KeyMaterial::Rsa(pair) => {
+                let rng = ring::rand::SystemRandom::new();
+                let mut signature = vec![0u8; pair.public().modulus_len()];
+                pair.sign(&ring::signature::RSA_PKCS1_SHA256, &rng, data, &mut signature)
+                    .map_err(|_| DkimError::Signing)?;
+                Ok(signature)
+            }
+            // RFC 8463: PureEdDSA over the SHA-256 of the signing input
+            KeyMaterial::Ed25519(pair) => Ok(pair.sign(&Sha256::digest(data)).as_ref().to_vec()),
+        }
+    }
+}
+
+impl std::fmt::Debug for DkimPrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DkimPrivateKey")
+            .field("algorithm", &self.algorithm())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Key bound to a selector
+#[derive(Debug)]
+pub struct SigningKey {
+    pub selector: String,
+    pub created_at: DateTime<Utc>,
+    key: DkimPrivateKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    /// Published, signing from `activate_at`
+    Pending { activate_at: DateTime<Utc> },
+    Active,
+    /// Replaced; DNS record still needed for in-flight verification
+    Retiring { since: DateTime<Utc> },
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectorInfo {
+    pub selector: String,
+    pub algorithm: DkimAlgorithm,
+    pub state: KeyState,
+    pub created_at: DateTime<Utc>,
+    pub dns_record: String,
+}
+
+struct DomainKeys {
+    active: Arc<SigningKey>,
+    pending: Option<(Arc<SigningKey>, DateTime<Utc>)>,
+    retiring: Vec<(Arc<SigningKey>, DateTime<Utc>)>,
+}
+
+impl DomainKeys {
+    /// Promote the staged key once its activation time has passed
+    fn promote(&mut self, now: DateTime<Utc>) {
+        match self.pending.take() {
+            Some((next, at)) if at <= now => {
+                let previous = std::mem::replace(&mut self.active, next);
+                self.retiring.push((previous, at));
+                tracing::info!("DKIM selector {} now active", self.active.selector);
+            }
+            pending => self.pending = pending,
+        }
+    }
+
+    fn all(&self) -> impl Iterator<Item = (&Arc<SigningKey>, KeyState)> {
+        let pending = self.pending.iter()
+            .map(|(key, at)| (key, KeyState::Pending { activate_at: *at }));
+        let retiring = self.retiring.iter()
+            .map(|(key, since)| (key, KeyState::Retiring { since: *since }));
+        std::iter::once((&self.active, KeyState::Active)).chain(pending).chain(retiring)
+    }
+}
+
+// =============================================================================
+// Signer
+// =============================================================================
+
+/// Per-domain signing options
+#[derive(Debug, Clone)]
+pub struct SigningOptions {
+    pub canonicalization: Canonicalization,
+    /// Header fields signed when present (From is always signed)
+    pub headers: Vec<String>,
+    /// Header fields signed one extra time
+    pub oversign: Vec<String>,
+    /// Signature lifetime (`x=` tag); `None` omits it
+    pub expiry: Option<Duration>,
+}
+
+impl Default for SigningOptions {
+    fn default() -> Self {
+        Self {
+            canonicalization: Canonicalization::default(),
+            headers: DEFAULT_SIGNED_HEADERS.iter().map(|h| h.to_string()).collect(),
+            oversign: DEFAULT_OVERSIGNED_HEADERS.iter().map(|h| h.to_string()).collect(),
+            expiry: Some(Duration::days(7)),
+        }
+    }
+}
+
+/// DKIM signer for outbound mail
+pub struct DkimSigner {
+    /// Keys per signing domain
+    domains: dashmap::DashMap<String, DomainKeys>,
+    /// Per-domain option overrides
+    domain_options: dashmap::DashMap<String, SigningOptions>,
+    /// Options for domains without an override
+    default_options: SigningOptions,
+}
+
+impl DkimSigner {
+    pub fn new() -> Self {
+        Self {
+            domains: dashmap::DashMap::new(),
+            domain_options: dashmap::DashMap::new(),
+            default_options: SigningOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: SigningOptions) -> Self {
+        self.default_options = options;
+        self
+    }
+
+    /// Override signing options for one domain
+    pub fn set_domain_options(&self, domain: &str, options: SigningOptions) {
+        self.domain_options.insert(domain.to_lowercase(), options);
+    }
+
+    /// Install a key that signs immediately, replacing any existing keys
+    /// for the domain
+    pub fn add_key(&self, domain: &str, selector: &str, key: DkimPrivateKey) {
+        self.domains.insert(domain.to_lowercase(), DomainKeys {
+            active: Arc::new(SigningKey {
+                selector: selector.to_string(),
+                created_at: Utc::now(),
+                key,
+            }),
+            pending: None,
+            retiring: Vec::new(),
+        });
+    }
+
+    /// Stage the next key for a domain. Publish its DNS record before
+    /// `activate_at`; the current key keeps signing until then.
+    pub fn stage_rotation(
+        &self,
+        domain: &str,
+        selector: &str,
+        key: DkimPrivateKey,
+        activate_at: DateTime<Utc>,
+    ) -> Result<(), DkimError> {
+        let domain = domain.to_lowercase();
+        let mut keys = self.domains.get_mut(&domain)
+            .ok_or_else(|| DkimError::NoKey(domain.clone()))?;
+
+        if keys.all().any(|(k, _)| k.selector == selector) {
+            return Err(DkimError::SelectorInUse(selector.to_string()));
+        }
+
+        keys.pending = Some((
+            Arc::new(SigningKey {
+                selector: selector.to_string(),
+                created_at: Utc::now(),
+                key,
+            }),
+            activate_at,
+        ));
+        Ok(())
+    }
+
+    /// Drop retiring keys older than `grace` and return their
+    /// `(domain, selector)` pairs so the DNS records can be withdrawn
+    pub fn retire_expired(&self, grace: Duration) -> Vec<(String, String)> {
+        let now = Utc::now();
+        let mut retired = Vec::new();
+
+        for mut entry in self.domains.iter_mut() {
+            let domain = entry.key().clone();
+            let keys = entry.value_mut();
+            keys.promote(now);
+            keys.retiring.retain(|(key, since)| {
+                let keep = now - *since < grace;
+                if !keep {
+                    retired.push((domain.clone(), key.selector.clone()));
+                }
+                keep
+            });
+        }
+
+        retired
+    }
+
+    /// Domains whose signing key is older than `max_age` and has no
+    /// rotation staged
+    pub fn rotation_due(&self, max_age: Duration) -> Vec<String> {
+        let now = Utc::now();
+        self.domains.iter()
+            .filter(|e| e.pending.is_none() && now - e.active.created_at >= max_age)
+            .map(|e| e.key().clone())
+            .collect()
+    }
+
+    /// Selectors known for a domain with their state and DNS records
+    pub fn selectors(&self, domain: &str) -> Vec<SelectorInfo> {
+        let Some(mut keys) = self.domains.get_mut(&domain.to_lowercase()) else {
+            return Vec::new();
+        };
+        keys.promote(Utc::now());
+
+        keys.all()
+            .map(|(key, state)| SelectorInfo {
+                selector: key.selector.clone(),
+                algorithm: key.key.algorithm(),
+                state,
+                created_at: key.created_at,
+                dns_record: key.key.dns_record(),
+            })
+            .collect()
+    }
+
+    pub fn has_key(&self, domain: &str) -> bool {
+        self.domains.contains_key(&domain.to_lowercase())
+    }
+
+    fn active_key(&self, domain: &str) -> Option<Arc<SigningKey>> {
+        let mut keys = self.domains.get_mut(domain)?;
+        keys.promote(Utc::now());
+        Some(keys.active.clone())
+    }
+
+    /// Sign a raw RFC 5322 message as its From domain. Returns the complete
+    /// `DKIM-Signature` header field, CRLF-terminated, to prepend to the
+    /// message. Bare LF line endings are signed as CRLF.
+    pub fn sign(&self, raw: &[u8]) -> Result<String, DkimError> {
+        let message = normalize_line_endings(raw);
+        self.sign_normalized(&message, Utc::now())
+    }
+
+    /// Sign a raw message and return it with the signature prepended
+    pub fn sign_message(&self, raw: &[u8]) -> Result<Vec<u8>, DkimError> {
+        let message = normalize_line_endings(raw);
+        let header = self.sign_normalized(&message, Utc::now())?;
+
+        let mut signed = Vec::with_capacity(header.len() + message.len());
+        signed.extend_from_slice(header.as_bytes());
+        signed.extend_from_slice(&message);
+        Ok(signed)
+    }
+
+    fn sign_normalized(&self, message: &[u8], now: DateTime<Utc>) -> Result<String, DkimError> {
+        let (header_block, body) = split_message(message);
+        let fields = parse_header_fields(header_block);
+
+        let from = fields.iter()
+            .rev()
+            .find(|f| f.name.eq_ignore_ascii_case("from"))
+            .ok_or(DkimError::NoFromHeader)?;
+        let domain = super::extract_domain(&from.unfolded_value());
+        if domain.is_empty() {
+            return Err(DkimError::NoFromHeader);
+        }
+
+        let key = self.active_key(&domain).ok_or_else(|| DkimError::NoKey(domain.clone()))?;
+        let options = self.domain_options.get(&domain)
+            .map(|o| o.clone())
+            .unwrap_or_else(|| self.default_options.clone());
+        let canon = options.canonicalization;
+
+        let body_hash = b64.encode(Sha256::digest(canonicalize_body(body, canon.body)));
+
+        // Signed header list; later instances of a field are signed first
+        let mut names: Vec<String> = Vec::new();
+        for name in std::iter::once("from")
+            .chain(options.headers.iter().map(String::as_str))
+            .chain(options.oversign.iter().map(String::as_str))
+        {
+            let name = name.to_ascii_lowercase();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let mut signed_names = Vec::new();
+        let mut data = Vec::new();
+        for name in &names {
+            for field in fields.iter().rev().filter(|f| f.name.eq_ignore_ascii_case(name)) {
+                signed_names.push(name.as_str());
+                data.extend(canonicalize_header(field.raw, canon.header));
+            }
+            // A listed name with no remaining instance signs as empty
+            if options.oversign.iter().any(|o| o.eq_ignore_ascii_case(name)) {
+                signed_names.push(name.as_str());
+            }
+        }
+
+        let mut tags = format!(
+            "v=1; a={}; c={}/{}; d={}; s={}; t={}",
+            algorithm_tag(key.key.algorithm()),
+            canonicalization_tag(canon.header),
+            canonicalization_tag(canon.body),
+            domain,
+            key.selector,
+            now.timestamp(),
+        );
+        if let Some(expiry) = options.expiry {
+            tags.push_str(&format!("; x={}", (now + expiry).timestamp()));
+        }
+        let unsigned = format!(
+            "DKIM-Signature: {};\r\n\th={};\r\n\tbh={};\r\n\tb=",
+            tags,
+            signed_names.join(":"),
+            body_hash,
+        );
+
+        // The signature header itself is signed with an empty b= and no
+        // trailing CRLF
+        data.extend(canonicalize_header(unsigned.as_bytes(), canon.header));
+        let signature = b64.encode(key.key.sign(&data)?);
+
+        Ok(format!("{}{}\r\n", unsigned, fold(&signature)))
+    }
+}
+
+impl Default for DkimSigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DkimError {
+    InvalidKey(String),
+    NoFromHeader,
+    /// No signing key for the From domain
+    NoKey(String),
+    SelectorInUse(String),
+    Signing,
+}
+
+impl std::fmt::Display for DkimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidKey(e) => write!(f, "Invalid DKIM key: {}", e),
+            Self::NoFromHeader => write!(f, "Message has no usable From header"),
+            Self::NoKey(domain) => write!(f, "No DKIM key for {}", domain),
+            Self::SelectorInUse(selector) => write!(f, "Selector already in use: {}", selector),
+            Self::Signing => write!(f, "DKIM signing failed"),
+        }
+    }
+}
+
+impl std::error::Error for DkimError {}
+
+// =============================================================================
+// Canonicalization (RFC 6376 §3.4)
+// =============================================================================
+
+struct HeaderField<'a> {
+    name: &'a str,
+    /// Whole field including continuation lines and the trailing CRLF
+    raw: &'a [u8],
+}
+
+impl HeaderField<'_> {
+    fn unfolded_value(&self) -> String {
+        let colon = self.raw.iter().position(|b| *b == b':').unwrap_or(0);
+        String::from_utf8_lossy(&self.raw[colon + 1..])
+            .replace("\r\n", "")
+            .trim()
+            .to_string()
+    }
+}
+
+fn algorithm_tag(algorithm: DkimAlgorithm) -> &'static str {
+    match algorithm {
+        DkimAlgorithm::RsaSha256 => "rsa-sha256",
+        DkimAlgorithm::RsaSha1 => "rsa-sha1",
+        DkimAlgorithm::Ed25519Sha256 => "ed25519-sha256",
+    }
+}
+
+fn canonicalization_tag(canonicalization: CanonicalizationType) -> &'static str {
+    match canonicalization {
+        CanonicalizationType::Simple => "simple",
+        CanonicalizationType::Relaxed => "relaxed",
+    }
+}
+
+fn normalize_line_endings(raw: &[u8]) -> Cow<'_, [u8]> {
+    let bare_lf = |i: usize| raw[i] == b'\n' && (i == 0 || raw[i - 1] != b'\r');
+    if !(0..raw.len()).any(bare_lf) {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut out = Vec::with_capacity(raw.len() + raw.len() / 32);
+    for (i, &b) in raw.iter().enumerate() {
+        if bare_lf(i) {
+            out.push(b'\r');
+        }
+        out.push(b);
+    }
+    Cow::Owned(out)
+}
+
+/// Split at the first empty line into (header block with its final CRLF, body)
+fn split_message(message: &[u8]) -> (&[u8], &[u8]) {
+    match message.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(i) => (&message[..i + 2], &message[i + 4..]),
+        None => (message, &[]),
+    }
+}
+
+fn parse_header_fields(block: &[u8]) -> Vec<HeaderField<'_>> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    let mut pos = 0;
+
+    while pos < block.len() {
+        let end = block[pos..].windows(2)
+            .position(|w| w == b"\r\n")
+            .map(|i| pos + i + 2)
+            .unwrap_or(block.len());
+        if !matches!(block[pos], b' ' | b'\t') {
+            if let Some(s) = start.take() {
+                push_field(&mut fields, &block[s..pos]);
+            }
+            start = Some(pos);
+        }
+        pos = end;
+    }
+    if let Some(s) = start {
+        push_field(&mut fields, &block[s..]);
+    }
+
+    fields
+}
+
+fn push_field<'a>(fields: &mut Vec<HeaderField<'a>>, raw: &'a [u8]) {
+    let Some(colon) = raw.iter().position(|b| *b == b':') else { return };
+    if let Ok(name) = std::str::from_utf8(&raw[..colon]) {
+        fields.push(HeaderField { name: name.trim(), raw });
+    }
+}
+
+fn canonicalize_header(raw: &[u8], canonicalization: CanonicalizationType) -> Vec<u8> {
+    match canonicalization {
+        CanonicalizationType::Simple => raw.to_vec(),
+        CanonicalizationType::Relaxed => {
+            let colon = raw.iter().position(|b| *b == b':').unwrap_or(raw.len());
+            let name = String::from_utf8_lossy(&raw[..colon]).trim().to_ascii_lowercase();
+
+            let mut out = name.into_bytes();
+            out.push(b':');
+            // Unfold, collapse whitespace runs, trim both ends of the value
+            let mut space = false;
+            let mut started = false;
+            for &b in raw.get(colon + 1..).unwrap_or(&[]) {
+                match b {
+                    b'\r' | b'\n' => {}
+                    b' ' | b'\t' => space = true,
+                    _ => {
+                        if space && started {
+                            out.push(b' ');
+                        }
+                        space = false;
+                        started = true;
+                        out.push(b);
+                    }
+                }
+            }
+            if raw.ends_with(b"\r\n") {
+                out.extend_from_slice(b"\r\n");
+            }
+            out
+        }
+    }
+}
+
+fn canonicalize_body(body: &[u8], canonicalization: CanonicalizationType) -> Vec<u8> {
+    let mut out = match canonicalization {
+        CanonicalizationType::Simple => {
+            let mut out = body.to_vec();
+            if !out.is_empty() && !out.ends_with(b"\r\n") {
+                out.extend_from_slice(b"\r\n");
+            }
+            out
+        }
+        CanonicalizationType::Relaxed => {
+            let mut out = Vec::with_capacity(body.len());
+            for line in body_lines(body) {
+                let mut space = false;
+                for &b in line {
+                    if b == b' ' || b == b'\t' {
+                        space = true;
+                    } else {
+                        if space {
+                            out.push(b' ');
+                        }
+                        space = false;
+                        out.push(b);
+                    }
+                }
+                out.extend_from_slice(b"\r\n");
+            }
+            out
+        }
+    };
+
+    // Trailing empty lines are ignored
+    while out.ends_with(b"\r\n\r\n") {
+        out.truncate(out.len() - 2);
+    }
+
+    match canonicalization {
+        // An empty simple body is a single CRLF
+        CanonicalizationType::Simple if out.is_empty() => b"\r\n".to_vec(),
+        CanonicalizationType::Relaxed if out == b"\r\n" => Vec::new(),
+        _ => out,
+    }
+}
+
+fn body_lines(body: &[u8]) -> Vec<&[u8]> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i + 1 < body.len() {
+        if body[i] == b'\r' && body[i + 1] == b'\n' {
+            lines.push(&body[start..i]);
+            i += 2;
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    if start < body.len() {
+        lines.push(&body[start..]);
+    }
+    lines
+}
+
+/// Fold a base64 tag value across continuation lines
+fn fold(value: &str) -> String {
+    value.as_bytes()
+        .chunks(72)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\r\n\t")
+}
+
+// =============================================================================
+// Key Encoding
+// =============================================================================
+
+fn decode_pem(pem: &str) -> Result<(String, Vec<u8>), DkimError> {
+    let mut label = None;
+    let mut body = String::new();
+
+    for line in pem.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("-----BEGIN ") {
+            label = rest.strip_suffix("-----").map(str::to_string);
+        } else if line.starts_with("-----END ") {
+            break;
+        } else if label.is_some() {
+            body.push_str(line);
+        }
+    }
+
+    let label = label.ok_or_else(|| DkimError::InvalidKey("no PEM block".to_string()))?;
+    let der = b64.decode(body).map_err(|e| DkimError::InvalidKey(e.to_string()))?;
+    Ok((label, der))
+}
+
+/// Wrap a PKCS#1 RSAPublicKey in a SubjectPublicKeyInfo, the form most
+/// verifiers expect in the `p=` tag
+fn rsa_spki(pkcs1: &[u8]) -> Vec<u8> {
+    // AlgorithmIdentifier { rsaEncryption, NULL }
+    const ALGORITHM: &[u8] = &[
+        0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+    ];
+
+    let mut bit_string = vec![0x00];
+    bit_string.extend_from_slice(pkcs1);
+
+    let mut body = ALGORITHM.to_vec();
+    body.extend(der_tlv(0x03, &bit_string));
+    der_tlv(0x30, &body)
+}
+
+fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
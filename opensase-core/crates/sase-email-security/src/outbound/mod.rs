@@ -2,8 +2,11 @@
 //!
 //! DLP, DKIM signing, encryption, and rate limiting for outbound emails.
 
+pub mod dkim;
+
+pub use dkim::{DkimError, DkimPrivateKey, DkimSigner, SigningOptions};
+
 use crate::{EmailMessage, EmailEnvelope};
-use std::collections::HashMap;
 use std::net::IpAddr;
 
 /// Outbound email processor
@@ -28,6 +31,16 @@ impl OutboundProcessor {
         }
     }
     
+    pub fn with_dkim_signer(mut self, signer: DkimSigner) -> Self {
+        self.dkim_signer = signer;
+        self
+    }
+    
+    /// DKIM keys and signing options
+    pub fn dkim(&self) -> &DkimSigner {
+        &self.dkim_signer
+    }
+    
     /// Process outbound email
     pub async fn process(&self, message: &EmailMessage) -> OutboundResult {
         let mut result = OutboundResult::default();
@@ -66,15 +79,36 @@ impl OutboundProcessor {
             }
         }
         
-        // 3. DKIM signing
-        result.dkim_signed = true;
-        
-        // 4. Determine TLS requirements
+        // 3. Determine TLS requirements
         result.require_tls = self.tls_policy.requires_tls(&message.headers.to);
         
         result.action = OutboundAction::Send;
         result
     }
+    
+    /// Process outbound email and DKIM-sign the raw message if it may be
+    /// sent. The signature header is returned for the relay to prepend.
+    pub async fn process_raw(&self, message: &EmailMessage, raw: &[u8]) -> OutboundResult {
+        let mut result = self.process(message).await;
+        if result.action != OutboundAction::Send {
+            return result;
+        }
+        
+        match self.dkim_signer.sign(raw) {
+            Ok(header) => {
+                result.dkim_signed = true;
+                result.dkim_signature = Some(header);
+            }
+            Err(DkimError::NoKey(domain)) => {
+                tracing::debug!("No DKIM key for {}, sending unsigned", domain);
+            }
+            Err(e) => {
+                tracing::warn!("DKIM signing failed for {}: {}", message.id, e);
+            }
+        }
+        
+        result
+    }
 }
 
 impl Default for OutboundProcessor {
@@ -88,6 +122,8 @@ pub struct OutboundResult {
     pub action: OutboundAction,
     pub reason: Option<String>,
     pub dkim_signed: bool,
+    /// `DKIM-Signature` header field to prepend when signed
+    pub dkim_signature: Option<String>,
     pub require_tls: bool,
     pub dlp_violations: usize,
 }
@@ -101,66 +137,6 @@ pub enum OutboundAction {
     Defer,
 }
 
-/// DKIM signer
-pub struct DkimSigner {
-    /// Signing keys per domain
-    keys: HashMap<String, DkimKey>,
-    /// Default selector
-    default_selector: String,
-}
-
-struct DkimKey {
-    selector: String,
-    private_key: Vec<u8>,
-    algorithm: DkimAlgorithm,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum DkimAlgorithm {
-    RsaSha256,
-    Ed25519Sha256,
-}
-
-impl DkimSigner {
-    pub fn new() -> Self {
-        Self {
-            keys: HashMap::new(),
-            default_selector: "default".to_string(),
-        }
-    }
-    
-    /// Sign message with DKIM
-    pub fn sign(&self, message: &EmailMessage) -> Option<String> {
-        let from_domain = extract_domain(&message.headers.from);
-        
-        let key = self.keys.get(&from_domain)?;
-        
-        // Create DKIM-Signature header
-        // In production: use proper DKIM library
-        let signature = format!(
-            "v=1; a=rsa-sha256; c=relaxed/relaxed; d={}; s={}; h=from:to:subject:date;",
-            from_domain, key.selector
-        );
-        
-        Some(signature)
-    }
-    
-    /// Add signing key for domain
-    pub fn add_key(&mut self, domain: &str, selector: &str, private_key: Vec<u8>) {
-        self.keys.insert(domain.to_string(), DkimKey {
-            selector: selector.to_string(),
-            private_key,
-            algorithm: DkimAlgorithm::RsaSha256,
-        });
-    }
-}
-
-impl Default for DkimSigner {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 /// Rate limiter for outbound emails
 pub struct RateLimiter {
     /// Per-sender limits
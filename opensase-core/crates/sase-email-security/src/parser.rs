@@ -4,6 +4,7 @@
 
 use crate::{EmailMessage, EmailHeaders, EmailBody, ContentType, Attachment, NestedFile, ExtractedUrl, UrlContext};
 use std::collections::HashMap;
+use mail_parser::MimeHeaders;
 use sha2::{Sha256, Digest};
 
 /// Email parser for MIME messages
@@ -114,9 +115,20 @@ impl EmailParser {
             body.text_html = Some(body_text.to_string());
         }
         
-        // Extract attachments (simplified - would use mail-parser in production)
-        // TODO: Full MIME multipart parsing
-        
+        // Extract attachments
+        if let Some(parsed) = mail_parser::MessageParser::default().parse(raw) {
+            for part in parsed.attachments() {
+                let filename = part.attachment_name().unwrap_or("attachment");
+                let content_type = part.content_type()
+                    .map(|ct| match ct.subtype() {
+                        Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                        None => ct.ctype().to_string(),
+                    })
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                attachments.push(Attachment::from_content(filename, &content_type, part.contents().to_vec()));
+            }
+        }
+
         Ok((body, attachments))
     }
     
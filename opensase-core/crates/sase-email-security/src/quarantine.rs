@@ -1,6 +1,7 @@
 //! Quarantine Management
 //!
-//! Email quarantine storage, review, and release functionality.
+//! Email quarantine storage, review, and release functionality. Also keeps
+//! the originals of messages delivered after CDR rebuilt their attachments.
 
 use crate::{EmailMessage, EmailVerdict, VerdictAction, ThreatCategory};
 use std::collections::HashMap;
@@ -24,11 +25,16 @@ pub struct QuarantinedMessage {
     pub status: QuarantineStatus,
     pub reviewed_by: Option<String>,
     pub notes: Vec<String>,
+    /// Original message as received, when kept
+    pub raw: Option<Vec<u8>>,
+    pub cdr_reports: Vec<crate::cdr::CdrReport>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum QuarantineStatus {
     Pending,
+    /// Original of a message delivered in sanitised form
+    Retained,
     Released,
     Deleted,
     Expired,
@@ -84,8 +90,45 @@ impl QuarantineManager {
             status: QuarantineStatus::Pending,
             reviewed_by: None,
             notes: Vec::new(),
+            raw: None,
+            cdr_reports: Vec::new(),
         };
         
+        self.insert(quarantined)
+    }
+    
+    /// Keep the original of a message CDR rebuilt or could not rebuild.
+    /// Held messages are pending review; originals of delivered ones are
+    /// retained so a reviewer can release them if the rebuild broke them.
+    pub fn retain_original(
+        &self,
+        message: EmailMessage,
+        verdict: EmailVerdict,
+        raw: Vec<u8>,
+        cdr_reports: Vec<crate::cdr::CdrReport>,
+    ) -> String {
+        let status = match verdict.action {
+            VerdictAction::Deliver | VerdictAction::DeliverModified => QuarantineStatus::Retained,
+            _ => QuarantineStatus::Pending,
+        };
+        
+        let quarantined = QuarantinedMessage {
+            id: message.id.clone(),
+            message,
+            verdict,
+            quarantined_at: chrono::Utc::now(),
+            status,
+            reviewed_by: None,
+            notes: Vec::new(),
+            raw: Some(raw),
+            cdr_reports,
+        };
+        
+        self.insert(quarantined)
+    }
+    
+    fn insert(&self, quarantined: QuarantinedMessage) -> String {
+        let id = quarantined.id.clone();
         self.messages.insert(id.clone(), quarantined);
         
         // Cleanup if over size limit
@@ -177,7 +220,7 @@ impl QuarantineManager {
         let mut entry = self.messages.get_mut(id)
             .ok_or(QuarantineError::NotFound)?;
         
-        if entry.status != QuarantineStatus::Pending {
+        if !matches!(entry.status, QuarantineStatus::Pending | QuarantineStatus::Retained) {
             return Err(QuarantineError::AlreadyProcessed);
         }
         
@@ -255,6 +298,7 @@ impl QuarantineManager {
             
             match entry.status {
                 QuarantineStatus::Pending => stats.pending += 1,
+                QuarantineStatus::Retained => stats.retained += 1,
                 QuarantineStatus::Released => stats.released += 1,
                 QuarantineStatus::Deleted => stats.deleted += 1,
                 QuarantineStatus::Expired => stats.expired += 1,
@@ -273,6 +317,7 @@ impl QuarantineManager {
 pub struct QuarantineStats {
     pub total: usize,
     pub pending: usize,
+    pub retained: usize,
     pub released: usize,
     pub deleted: usize,
     pub expired: usize,
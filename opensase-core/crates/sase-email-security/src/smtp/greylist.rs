@@ -0,0 +1,283 @@
+//! Greylisting
+//!
+//! Temporarily refuses the first delivery attempt of an unknown
+//! (client network, MAIL FROM, RCPT TO) triplet. Real MTAs retry after the
+//! minimum delay; most spamware never does. Clients are grouped by /24
+//! (IPv4) or /64 (IPv6) so sender pools rotating through nearby addresses
+//! still pass, and networks that keep retrying correctly are whitelisted
+//! so their mail is not delayed again.
+
+use chrono::{DateTime, Duration, Utc};
+use std::net::IpAddr;
+
+#[derive(Clone)]
+pub struct GreylistConfig {
+    pub enabled: bool,
+    /// Retries earlier than this are deferred again
+    pub min_retry_delay: Duration,
+    /// A triplet that is not retried within this window starts over
+    pub retry_window: Duration,
+    /// How long a passed triplet is remembered after its last use
+    pub pass_ttl: Duration,
+    /// Passed triplets before a client network is whitelisted
+    pub auto_whitelist_after: u32,
+    /// How long an auto-whitelist entry lasts without new mail
+    pub whitelist_ttl: Duration,
+    pub ipv4_prefix: u8,
+    pub ipv6_prefix: u8,
+    /// Networks never greylisted (address, prefix length)
+    pub exempt_networks: Vec<(IpAddr, u8)>,
+    /// Recipient domains that opt out
+    pub exempt_recipient_domains: Vec<String>,
+}
+
+impl Default for GreylistConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_retry_delay: Duration::minutes(5),
+            retry_window: Duration::hours(4),
+            pass_ttl: Duration::days(36),
+            auto_whitelist_after: 5,
+            whitelist_ttl: Duration::days(35),
+            ipv4_prefix: 24,
+            ipv6_prefix: 64,
+            exempt_networks: Vec::new(),
+            exempt_recipient_domains: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreylistDecision {
+    Pass(PassReason),
+    /// Try again in this many seconds
+    Defer { retry_after_secs: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassReason {
+    Disabled,
+    Exempt,
+    AutoWhitelisted,
+    /// Triplet already passed
+    Known,
+    /// First successful retry of this triplet
+    Retried,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Triplet {
+    network: String,
+    sender: String,
+    recipient: String,
+}
+
+#[derive(Debug, Clone)]
+struct TripletState {
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    attempts: u32,
+    passed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ClientNetwork {
+    passes: u32,
+    last_pass: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GreylistStats {
+    pub pending_triplets: usize,
+    pub passed_triplets: usize,
+    pub whitelisted_networks: usize,
+}
+
+/// Greylisting engine
+pub struct Greylist {
+    config: GreylistConfig,
+    triplets: dashmap::DashMap<Triplet, TripletState>,
+    networks: dashmap::DashMap<String, ClientNetwork>,
+}
+
+impl Greylist {
+    pub fn new(config: GreylistConfig) -> Self {
+        Self {
+            config,
+            triplets: dashmap::DashMap::new(),
+            networks: dashmap::DashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &GreylistConfig {
+        &self.config
+    }
+
+    pub fn check(&self, ip: IpAddr, mail_from: &str, rcpt_to: &str) -> GreylistDecision {
+        self.check_at(ip, mail_from, rcpt_to, Utc::now())
+    }
+
+    /// Decide on one recipient of a delivery attempt made at `now`
+    pub fn check_at(&self, ip: IpAddr, mail_from: &str, rcpt_to: &str, now: DateTime<Utc>) -> GreylistDecision {
+        if !self.config.enabled {
+            return GreylistDecision::Pass(PassReason::Disabled);
+        }
+        if self.is_exempt(ip, rcpt_to) {
+            return GreylistDecision::Pass(PassReason::Exempt);
+        }
+
+        let network = self.network_key(ip);
+        if self.is_whitelisted(&network, now) {
+            return GreylistDecision::Pass(PassReason::AutoWhitelisted);
+        }
+
+        let triplet = Triplet {
+            network: network.clone(),
+            sender: mail_from.to_lowercase(),
+            recipient: rcpt_to.to_lowercase(),
+        };
+        let mut state = self.triplets.entry(triplet).or_insert_with(|| TripletState {
+            first_seen: now,
+            last_seen: now,
+            attempts: 0,
+            passed: false,
+        });
+
+        // Forget passes and pending entries that have gone stale
+        let stale = if state.passed {
+            now - state.last_seen > self.config.pass_ttl
+        } else {
+            now - state.first_seen > self.config.retry_window
+        };
+        if stale {
+            *state = TripletState { first_seen: now, last_seen: now, attempts: 0, passed: false };
+        }
+
+        state.attempts += 1;
+        state.last_seen = now;
+
+        if state.passed {
+            return GreylistDecision::Pass(PassReason::Known);
+        }
+
+        let waited = now - state.first_seen;
+        if state.attempts > 1 && waited >= self.config.min_retry_delay {
+            state.passed = true;
+            drop(state);
+            self.note_pass(&network, now);
+            tracing::debug!("Greylist pass for {} after {}s", network, waited.num_seconds());
+            return GreylistDecision::Pass(PassReason::Retried);
+        }
+
+        GreylistDecision::Defer {
+            retry_after_secs: (self.config.min_retry_delay - waited).num_seconds().max(1),
+        }
+    }
+
+    fn note_pass(&self, network: &str, now: DateTime<Utc>) {
+        let mut client = self.networks.entry(network.to_string()).or_insert(ClientNetwork {
+            passes: 0,
+            last_pass: now,
+        });
+        client.passes += 1;
+        client.last_pass = now;
+        if client.passes == self.config.auto_whitelist_after {
+            tracing::info!("Auto-whitelisting {} after {} greylist passes", network, client.passes);
+        }
+    }
+
+    fn is_whitelisted(&self, network: &str, now: DateTime<Utc>) -> bool {
+        self.networks.get(network)
+            .map(|c| {
+                c.passes >= self.config.auto_whitelist_after
+                    && now - c.last_pass <= self.config.whitelist_ttl
+            })
+            .unwrap_or(false)
+    }
+
+    /// Keep a whitelisted network's entry alive on accepted mail
+    pub fn record_delivery(&self, ip: IpAddr) {
+        let network = self.network_key(ip);
+        if let Some(mut client) = self.networks.get_mut(&network) {
+            if client.passes >= self.config.auto_whitelist_after {
+                client.last_pass = Utc::now();
+            }
+        }
+    }
+
+    fn is_exempt(&self, ip: IpAddr, rcpt_to: &str) -> bool {
+        if self.config.exempt_networks.iter().any(|(net, prefix)| same_network(ip, *net, *prefix)) {
+            return true;
+        }
+        let domain = rcpt_to.rsplit('@').next().unwrap_or("").to_lowercase();
+        self.config.exempt_recipient_domains.iter().any(|d| d.eq_ignore_ascii_case(&domain))
+    }
+
+    fn network_key(&self, ip: IpAddr) -> String {
+        match ip {
+            IpAddr::V4(v4) => {
+                let prefix = self.config.ipv4_prefix.min(32);
+                let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+                let net = std::net::Ipv4Addr::from(u32::from(v4) & mask);
+                format!("{}/{}", net, prefix)
+            }
+            IpAddr::V6(v6) => {
+                let prefix = self.config.ipv6_prefix.min(128);
+                let mask = if prefix == 0 { 0 } else { u128::MAX << (128 - prefix) };
+                let net = std::net::Ipv6Addr::from(u128::from(v6) & mask);
+                format!("{}/{}", net, prefix)
+            }
+        }
+    }
+
+    /// Drop expired triplets and whitelist entries
+    pub fn prune(&self) -> usize {
+        let now = Utc::now();
+        let before = self.triplets.len() + self.networks.len();
+
+        self.triplets.retain(|_, state| {
+            if state.passed {
+                now - state.last_seen <= self.config.pass_ttl
+            } else {
+                now - state.first_seen <= self.config.retry_window
+            }
+        });
+        self.networks.retain(|_, client| now - client.last_pass <= self.config.whitelist_ttl);
+
+        before - (self.triplets.len() + self.networks.len())
+    }
+
+    pub fn stats(&self) -> GreylistStats {
+        let passed = self.triplets.iter().filter(|t| t.passed).count();
+        GreylistStats {
+            pending_triplets: self.triplets.len() - passed,
+            passed_triplets: passed,
+            whitelisted_networks: self.networks.iter()
+                .filter(|c| c.passes >= self.config.auto_whitelist_after)
+                .count(),
+        }
+    }
+}
+
+impl Default for Greylist {
+    fn default() -> Self {
+        Self::new(GreylistConfig::default())
+    }
+}
+
+fn same_network(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let prefix = prefix.min(32);
+            let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+            u32::from(a) & mask == u32::from(b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let prefix = prefix.min(128);
+            let mask = if prefix == 0 { 0 } else { u128::MAX << (128 - prefix) };
+            u128::from(a) & mask == u128::from(b) & mask
+        }
+        _ => false,
+    }
+}
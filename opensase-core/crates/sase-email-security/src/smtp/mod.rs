@@ -1,9 +1,18 @@
 //! High-Performance SMTP Server
 //!
 //! Production-grade SMTP server built on Tokio with full ESMTP support.
+//! Connection filtering: per-IP rate limits, greylisting at RCPT TO and a
+//! tarpit that slows down misbehaving clients.
+
+pub mod greylist;
+pub mod tarpit;
+
+pub use greylist::{Greylist, GreylistConfig, GreylistDecision, PassReason};
+pub use tarpit::{Strike, Tarpit, TarpitConfig};
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
@@ -12,6 +21,8 @@ pub struct SmtpServer {
     config: SmtpConfig,
     pipeline: Arc<crate::EmailSecurityGateway>,
     connection_tracker: ConnectionTracker,
+    greylist: Arc<Greylist>,
+    tarpit: Arc<Tarpit>,
 }
 
 #[derive(Clone)]
@@ -23,6 +34,8 @@ pub struct SmtpConfig {
     pub timeout_seconds: u64,
     pub require_tls: bool,
     pub rate_limits: RateLimitConfig,
+    pub greylisting: GreylistConfig,
+    pub tarpit: TarpitConfig,
 }
 
 impl Default for SmtpConfig {
@@ -35,6 +48,8 @@ impl Default for SmtpConfig {
             timeout_seconds: 300,
             require_tls: false,
             rate_limits: RateLimitConfig::default(),
+            greylisting: GreylistConfig::default(),
+            tarpit: TarpitConfig::default(),
         }
     }
 }
@@ -107,12 +122,38 @@ impl Default for ConnectionTracker {
 impl SmtpServer {
     pub fn new(config: SmtpConfig, pipeline: Arc<crate::EmailSecurityGateway>) -> Self {
         Self {
+            greylist: Arc::new(Greylist::new(config.greylisting.clone())),
+            tarpit: Arc::new(Tarpit::new(config.tarpit.clone())),
             config,
             pipeline,
             connection_tracker: ConnectionTracker::new(),
         }
     }
     
+    pub fn greylist(&self) -> &Arc<Greylist> {
+        &self.greylist
+    }
+    
+    pub fn tarpit(&self) -> &Arc<Tarpit> {
+        &self.tarpit
+    }
+    
+    /// Periodically drop expired greylist and tarpit state
+    pub fn spawn_maintenance(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let greylist = self.greylist.clone();
+        let tarpit = self.tarpit.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let removed = greylist.prune() + tarpit.prune();
+                if removed > 0 {
+                    tracing::debug!("SMTP maintenance removed {} expired entries", removed);
+                }
+            }
+        })
+    }
+    
     /// Start SMTP server
     pub async fn run(&self) -> Result<(), std::io::Error> {
         let listener = TcpListener::bind(&self.config.listen_addr).await?;
@@ -133,9 +174,13 @@ impl SmtpServer {
             
             let pipeline = self.pipeline.clone();
             let config = self.config.clone();
+            let filters = ConnectionFilters {
+                greylist: self.greylist.clone(),
+                tarpit: self.tarpit.clone(),
+            };
             
             tokio::spawn(async move {
-                if let Err(e) = Self::handle_connection(socket, peer_addr, config, pipeline).await {
+                if let Err(e) = Self::handle_connection(socket, peer_addr, config, pipeline, filters).await {
                     tracing::warn!("Connection error from {}: {}", peer_addr, e);
                 }
             });
@@ -147,9 +192,19 @@ impl SmtpServer {
         peer_addr: SocketAddr,
         config: SmtpConfig,
         pipeline: Arc<crate::EmailSecurityGateway>,
+        filters: ConnectionFilters,
     ) -> Result<(), SmtpError> {
+        let ip = peer_addr.ip();
         let mut session = SmtpSession::new(socket, peer_addr, config.clone());
         
+        // Clients must wait for the banner; spamware often doesn't
+        let pause = filters.tarpit.config().greeting_pause.max(filters.tarpit.delay(ip));
+        if !pause.is_zero() && session.client_spoke_within(pause).await {
+            tracing::debug!("Early talker from {}", peer_addr);
+            filters.tarpit.strike(ip, Strike::EarlyTalker);
+        }
+        session.tarpit = filters.tarpit.delay(ip);
+        
         // Send greeting
         session.send_response(220, &format!("{} ESMTP OpenSASE Email Gateway", config.hostname)).await?;
         
@@ -160,6 +215,12 @@ impl SmtpServer {
         loop {
             let command = match session.read_command().await {
                 Ok(cmd) => cmd,
+                Err(SmtpError::UnknownCommand) => {
+                    filters.tarpit.strike(ip, Strike::UnknownCommand);
+                    session.tarpit = filters.tarpit.delay(ip);
+                    session.send_response(500, "5.5.2 Command not recognized").await?;
+                    continue;
+                }
                 Err(_) => break,
             };
             
@@ -213,10 +274,22 @@ impl SmtpServer {
                 
                 (SessionState::MailFrom | SessionState::RcptTo, SmtpCommand::RcptTo(recipient)) => {
                     if envelope.rcpt_to.len() >= config.max_recipients {
+                        filters.tarpit.strike(ip, Strike::TooManyRecipients);
+                        session.tarpit = filters.tarpit.delay(ip);
                         session.send_response(452, "4.5.3 Too many recipients").await?;
                         continue;
                     }
                     
+                    let sender = envelope.mail_from.as_deref().unwrap_or("");
+                    if let GreylistDecision::Defer { retry_after_secs } = filters.greylist.check(ip, sender, &recipient) {
+                        tracing::debug!("Greylisted {} -> {} from {}", sender, recipient, peer_addr);
+                        session.send_response(
+                            451,
+                            &format!("4.7.1 Greylisted, please try again in {} seconds", retry_after_secs),
+                        ).await?;
+                        continue;
+                    }
+                    
                     envelope.rcpt_to.push(recipient);
                     session.send_response(250, "2.1.5 OK").await?;
                     state = SessionState::RcptTo;
@@ -248,16 +321,19 @@ impl SmtpServer {
                     let parser = crate::parser::EmailParser::new();
                     match parser.parse(&data_buffer, email_envelope) {
                         Ok(message) => {
-                            let verdict = pipeline.process(&message).await;
+                            let processed = pipeline.process_raw(&data_buffer, &message).await;
                             
-                            match verdict.action {
+                            match processed.verdict.action {
                                 crate::VerdictAction::Deliver | crate::VerdictAction::DeliverModified => {
+                                    filters.greylist.record_delivery(ip);
                                     session.send_response(250, "2.0.0 OK: Message accepted").await?;
                                 }
                                 crate::VerdictAction::Quarantine => {
                                     session.send_response(250, "2.0.0 OK: Message accepted").await?;
                                 }
                                 crate::VerdictAction::Reject => {
+                                    filters.tarpit.strike(ip, Strike::RejectedMessage);
+                                    session.tarpit = filters.tarpit.delay(ip);
                                     session.send_response(550, "5.7.1 Message rejected").await?;
                                 }
                                 crate::VerdictAction::Drop => {
@@ -281,6 +357,8 @@ impl SmtpServer {
                 }
                 
                 _ => {
+                    filters.tarpit.strike(ip, Strike::BadSequence);
+                    session.tarpit = filters.tarpit.delay(ip);
                     session.send_response(503, "5.5.1 Bad sequence of commands").await?;
                 }
             }
@@ -290,6 +368,12 @@ impl SmtpServer {
     }
 }
 
+/// Shared filtering state handed to each connection
+struct ConnectionFilters {
+    greylist: Arc<Greylist>,
+    tarpit: Arc<Tarpit>,
+}
+
 /// SMTP session handler
 struct SmtpSession {
     reader: BufReader<tokio::io::ReadHalf<TcpStream>>,
@@ -297,6 +381,8 @@ struct SmtpSession {
     peer_addr: SocketAddr,
     #[allow(dead_code)]
     config: SmtpConfig,
+    /// Delay before every response (tarpitted clients)
+    tarpit: Duration,
 }
 
 impl SmtpSession {
@@ -307,10 +393,22 @@ impl SmtpSession {
             writer,
             peer_addr,
             config,
+            tarpit: Duration::ZERO,
+        }
+    }
+    
+    /// Wait up to `window`; true if the client sent anything meanwhile
+    async fn client_spoke_within(&mut self, window: Duration) -> bool {
+        match tokio::time::timeout(window, self.reader.fill_buf()).await {
+            Ok(Ok(buf)) => !buf.is_empty(),
+            _ => false,
         }
     }
     
     async fn send_response(&mut self, code: u16, message: &str) -> Result<(), SmtpError> {
+        if !self.tarpit.is_zero() {
+            tokio::time::sleep(self.tarpit).await;
+        }
         let response = format!("{} {}\r\n", code, message);
         self.writer.write_all(response.as_bytes()).await?;
         self.writer.flush().await?;
@@ -318,6 +416,9 @@ impl SmtpSession {
     }
     
     async fn send_ehlo_response(&mut self, hostname: &str, capabilities: &[String]) -> Result<(), SmtpError> {
+        if !self.tarpit.is_zero() {
+            tokio::time::sleep(self.tarpit).await;
+        }
         self.writer.write_all(format!("250-{}\r\n", hostname).as_bytes()).await?;
         
         for (i, cap) in capabilities.iter().enumerate() {
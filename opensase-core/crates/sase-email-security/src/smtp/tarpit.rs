@@ -0,0 +1,132 @@
+//! SMTP Tarpit
+//!
+//! Slows down clients that misbehave. Each protocol violation or rejection
+//! adds a strike for the client address; strikes decay over time. Clients
+//! with strikes get every response delayed, doubling per strike up to a
+//! cap, which ties up spamware connections cheaply while legitimate MTAs
+//! that trip one rule barely notice.
+
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct TarpitConfig {
+    pub enabled: bool,
+    /// Delay at the first strike; doubles with each further strike
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Time for a client's strikes to halve
+    pub strike_half_life: Duration,
+    /// Pause before the banner for every client; anything the client
+    /// sends in this window marks it as an early talker
+    pub greeting_pause: Duration,
+}
+
+impl Default for TarpitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(30),
+            strike_half_life: Duration::from_secs(30 * 60),
+            greeting_pause: Duration::from_secs(0),
+        }
+    }
+}
+
+/// Suspicious client behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strike {
+    /// Sent commands before the greeting
+    EarlyTalker,
+    UnknownCommand,
+    BadSequence,
+    TooManyRecipients,
+    /// Message rejected by the security pipeline
+    RejectedMessage,
+}
+
+impl Strike {
+    fn weight(&self) -> f64 {
+        match self {
+            Self::EarlyTalker => 3.0,
+            Self::RejectedMessage => 2.0,
+            Self::UnknownCommand | Self::BadSequence | Self::TooManyRecipients => 1.0,
+        }
+    }
+}
+
+struct ClientStrikes {
+    score: f64,
+    updated: Instant,
+}
+
+/// Per-client strike tracking
+pub struct Tarpit {
+    config: TarpitConfig,
+    clients: dashmap::DashMap<IpAddr, ClientStrikes>,
+}
+
+impl Tarpit {
+    pub fn new(config: TarpitConfig) -> Self {
+        Self {
+            config,
+            clients: dashmap::DashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &TarpitConfig {
+        &self.config
+    }
+
+    fn decayed(&self, strikes: &ClientStrikes, now: Instant) -> f64 {
+        let half_life = self.config.strike_half_life.as_secs_f64().max(1.0);
+        let elapsed = now.duration_since(strikes.updated).as_secs_f64();
+        strikes.score * 0.5f64.powf(elapsed / half_life)
+    }
+
+    pub fn strike(&self, ip: IpAddr, strike: Strike) {
+        if !self.config.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let mut entry = self.clients.entry(ip).or_insert(ClientStrikes { score: 0.0, updated: now });
+        entry.score = self.decayed(&entry, now) + strike.weight();
+        entry.updated = now;
+        tracing::debug!("Tarpit strike for {}: {:?} (score {:.1})", ip, strike, entry.score);
+    }
+
+    /// Current strike score
+    pub fn score(&self, ip: IpAddr) -> f64 {
+        self.clients.get(&ip)
+            .map(|s| self.decayed(&s, Instant::now()))
+            .unwrap_or(0.0)
+    }
+
+    /// Delay to apply before each response to this client
+    pub fn delay(&self, ip: IpAddr) -> Duration {
+        if !self.config.enabled {
+            return Duration::ZERO;
+        }
+        let score = self.score(ip);
+        if score < 1.0 {
+            return Duration::ZERO;
+        }
+        let factor = 2f64.powf((score - 1.0).min(16.0));
+        self.config.base_delay.mul_f64(factor).min(self.config.max_delay)
+    }
+
+    /// Forget clients whose strikes have decayed away
+    pub fn prune(&self) -> usize {
+        let now = Instant::now();
+        let before = self.clients.len();
+        self.clients.retain(|_, s| self.decayed(s, now) >= 0.1);
+        before - self.clients.len()
+    }
+}
+
+impl Default for Tarpit {
+    fn default() -> Self {
+        Self::new(TarpitConfig::default())
+    }
+}
@@ -0,0 +1,159 @@
+//! Block Page Module - User-facing block notifications
+//!
+//! Tenant-branded pages that explain which policy blocked a request,
+//! an exception-request workflow, and click-through telemetry.
+
+mod page;
+mod service;
+
+pub use page::{BlockPageRenderer, DEFAULT_TEMPLATE};
+pub use service::{BlockPageService, ExceptionSink, WebhookExceptionSink};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Enforcement point that produced the block
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockSource {
+    UrlFilter,
+    Dlp,
+    Ztna,
+}
+
+impl BlockSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UrlFilter => "Web filtering",
+            Self::Dlp => "Data loss prevention",
+            Self::Ztna => "Private access",
+        }
+    }
+}
+
+/// Block reported by an enforcement point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockContext {
+    /// Tenant ID
+    pub tenant_id: String,
+    
+    /// User ID, when known
+    pub user_id: Option<String>,
+    
+    /// Enforcement point
+    pub source: BlockSource,
+    
+    /// Policy that triggered
+    pub policy_id: String,
+    
+    /// Human-readable policy name
+    pub policy_name: String,
+    
+    /// Why the request was blocked
+    pub reason: String,
+    
+    /// URL, application or file that was blocked
+    pub resource: String,
+    
+    /// URL category, DLP data type, ...
+    pub category: Option<String>,
+}
+
+/// Recorded block, addressable by its page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockEvent {
+    pub id: String,
+    #[serde(flatten)]
+    pub context: BlockContext,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Tenant branding for block pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantBranding {
+    pub company_name: String,
+    pub logo_url: Option<String>,
+    /// CSS color for the header
+    pub primary_color: String,
+    pub support_url: Option<String>,
+    pub support_email: Option<String>,
+    /// Extra text shown under the explanation
+    pub custom_message: Option<String>,
+    /// Offer the exception-request form
+    pub allow_exception_requests: bool,
+    /// Full template override (see `DEFAULT_TEMPLATE` placeholders)
+    pub template: Option<String>,
+}
+
+impl Default for TenantBranding {
+    fn default() -> Self {
+        Self {
+            company_name: "OpenSASE".to_string(),
+            logo_url: None,
+            primary_color: "#1f3a5f".to_string(),
+            support_url: None,
+            support_email: None,
+            custom_message: None,
+            allow_exception_requests: true,
+            template: None,
+        }
+    }
+}
+
+/// Exception request status
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExceptionStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+/// User request to lift a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExceptionRequest {
+    pub id: String,
+    pub block: BlockEvent,
+    pub requested_by: Option<String>,
+    pub justification: String,
+    pub status: ExceptionStatus,
+    /// Ticket/approval reference from the exception sink
+    pub ticket_ref: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    /// Approved exceptions lapse at this time
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// User interaction on a block page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PageInteraction {
+    Viewed,
+    ClickedSupport,
+    ClickedBack,
+    RequestedException,
+}
+
+/// Friction caused by one policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyFriction {
+    pub policy_id: String,
+    pub policy_name: String,
+    pub source: Option<BlockSource>,
+    pub blocks: u64,
+    pub page_views: u64,
+    pub support_clicks: u64,
+    pub back_clicks: u64,
+    pub exception_requests: u64,
+    pub exceptions_approved: u64,
+    pub distinct_users: usize,
+}
+
+impl PolicyFriction {
+    /// Ranking score: requests and support contacts weigh more than views
+    pub fn score(&self) -> u64 {
+        self.page_views + 3 * self.support_clicks + 5 * self.exception_requests
+    }
+}
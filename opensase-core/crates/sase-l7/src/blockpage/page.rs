@@ -0,0 +1,161 @@
+//! Block Page Renderer - Templated, tenant-branded HTML
+
+use crate::blockpage::{BlockEvent, TenantBranding};
+
+/// Default block page template
+///
+/// Placeholders: `{{company}}`, `{{logo}}`, `{{color}}`, `{{source}}`,
+/// `{{policy_name}}`, `{{reason}}`, `{{resource}}`, `{{category}}`,
+/// `{{block_id}}`, `{{occurred_at}}`, `{{custom_message}}`, `{{support}}`,
+/// `{{exception_form}}`, `{{base_path}}`.
+pub const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Access blocked - {{company}}</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 0; background: #f5f6f8; color: #222; }
+header { background: {{color}}; color: #fff; padding: 16px 24px; display: flex; align-items: center; gap: 12px; }
+header img { max-height: 36px; }
+main { max-width: 640px; margin: 32px auto; background: #fff; padding: 24px 32px; border-radius: 8px; }
+dt { font-weight: 600; margin-top: 12px; }
+dd { margin: 4px 0 0 0; word-break: break-all; }
+textarea { width: 100%; min-height: 80px; }
+.muted { color: #666; font-size: 0.9em; }
+</style>
+</head>
+<body>
+<header>{{logo}}<strong>{{company}}</strong></header>
+<main>
+<h1>This request was blocked</h1>
+<p>{{source}} blocked access under the <strong>{{policy_name}}</strong> policy.</p>
+<dl>
+<dt>Reason</dt><dd>{{reason}}</dd>
+<dt>Resource</dt><dd>{{resource}}</dd>
+{{category}}
+</dl>
+{{custom_message}}
+{{exception_form}}
+<p><a href="{{base_path}}/{{block_id}}/click?target=back" onclick="history.back(); fetch(this.href, {method: 'POST'}); return false;">Go back</a>{{support}}</p>
+<p class="muted">Reference {{block_id}} &middot; {{occurred_at}}</p>
+</main>
+</body>
+</html>
+"#;
+
+/// Renders block pages
+pub struct BlockPageRenderer {
+    base_path: String,
+}
+
+impl BlockPageRenderer {
+    /// Create renderer; `base_path` is where block pages are served
+    pub fn new(base_path: impl Into<String>) -> Self {
+        Self {
+            base_path: base_path.into().trim_end_matches('/').to_string(),
+        }
+    }
+    
+    /// Render the page for a block
+    pub fn render(&self, event: &BlockEvent, branding: &TenantBranding) -> String {
+        let context = &event.context;
+        let template = branding.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        
+        let logo = branding.logo_url.as_ref()
+            .map(|url| format!(r#"<img src="{}" alt="">"#, escape(url)))
+            .unwrap_or_default();
+        let category = context.category.as_ref()
+            .map(|c| format!("<dt>Category</dt><dd>{}</dd>", escape(c)))
+            .unwrap_or_default();
+        let custom_message = branding.custom_message.as_ref()
+            .map(|m| format!("<p>{}</p>", escape(m)))
+            .unwrap_or_default();
+        
+        let support = match (&branding.support_url, &branding.support_email) {
+            (Some(url), _) => format!(
+                r#" &middot; <a href="{base}/{id}/click?target=support&amp;to={to}" rel="noopener">Contact IT support</a>"#,
+                base = self.base_path,
+                id = event.id,
+                to = escape(&urlencoding::encode(url)),
+            ),
+            (None, Some(email)) => format!(
+                r#" &middot; <a href="mailto:{email}?subject=Blocked%20request%20{id}" onclick="fetch('{base}/{id}/click?target=support', {{method: 'POST'}})">Contact IT support</a>"#,
+                email = escape(email),
+                base = self.base_path,
+                id = event.id,
+            ),
+            (None, None) => String::new(),
+        };
+        
+        let exception_form = if branding.allow_exception_requests {
+            format!(
+                r#"<form method="post" action="{base}/{id}/exception">
+<p><label for="justification">Need access for work? Tell us why and we'll review it.</label></p>
+<textarea id="justification" name="justification" required maxlength="2000"></textarea>
+<p><button type="submit">Request an exception</button></p>
+</form>"#,
+                base = self.base_path,
+                id = event.id,
+            )
+        } else {
+            String::new()
+        };
+        
+        // Pre-rendered fragments are inserted as-is; everything else is escaped
+        let replacements = [
+            ("{{company}}", escape(&branding.company_name)),
+            ("{{logo}}", logo),
+            ("{{color}}", sanitize_color(&branding.primary_color)),
+            ("{{source}}", escape(context.source.as_str())),
+            ("{{policy_name}}", escape(&context.policy_name)),
+            ("{{reason}}", escape(&context.reason)),
+            ("{{resource}}", escape(&context.resource)),
+            ("{{category}}", category),
+            ("{{block_id}}", escape(&event.id)),
+            ("{{occurred_at}}", event.occurred_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+            ("{{custom_message}}", custom_message),
+            ("{{support}}", support),
+            ("{{exception_form}}", exception_form),
+            ("{{base_path}}", self.base_path.clone()),
+        ];
+        
+        let mut html = template.to_string();
+        for (placeholder, value) in &replacements {
+            html = html.replace(placeholder, value);
+        }
+        html
+    }
+}
+
+impl Default for BlockPageRenderer {
+    fn default() -> Self {
+        Self::new("/block")
+    }
+}
+
+/// HTML-escape text
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Only allow plain CSS colors into the stylesheet
+fn sanitize_color(color: &str) -> String {
+    let ok = color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#') && color.len() <= 32;
+    if ok && !color.is_empty() {
+        color.to_string()
+    } else {
+        TenantBranding::default().primary_color
+    }
+}
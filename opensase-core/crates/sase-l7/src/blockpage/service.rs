@@ -0,0 +1,643 @@
+//! Block Page Service - Block records, exception workflow, telemetry
+
+use crate::blockpage::{
+    BlockContext, BlockEvent, BlockPageRenderer, ExceptionRequest, ExceptionStatus,
+    PageInteraction, PolicyFriction, TenantBranding,
+};
+use crate::{L7Error, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post, put},
+    Form, Json, Router,
+};
+use chrono::{Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn, debug};
+
+/// Maximum justification length accepted from the page
+const MAX_JUSTIFICATION_LEN: usize = 2000;
+
+/// Opens tickets/approvals for exception requests
+#[async_trait::async_trait]
+pub trait ExceptionSink: Send + Sync {
+    /// Open a ticket; returns its reference
+    async fn open(&self, request: &ExceptionRequest) -> Result<String>;
+    
+    /// Report the decision back to the ticketing system
+    async fn decided(&self, _request: &ExceptionRequest) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Posts exception requests to a ticketing/approval webhook
+pub struct WebhookExceptionSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookExceptionSink {
+    /// Create sink for a webhook URL
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExceptionSink for WebhookExceptionSink {
+    async fn open(&self, request: &ExceptionRequest) -> Result<String> {
+        let response = self.client.post(&self.url)
+            .json(&serde_json::json!({
+                "event": "exception_requested",
+                "request": request,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        
+        // Ticketing systems differ; accept `ticket_id` or `id`, else use ours
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        let ticket = body.get("ticket_id")
+            .or_else(|| body.get("id"))
+            .and_then(|v| match v {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| request.id.clone());
+        
+        Ok(ticket)
+    }
+    
+    async fn decided(&self, request: &ExceptionRequest) -> Result<()> {
+        self.client.post(&self.url)
+            .json(&serde_json::json!({
+                "event": "exception_decided",
+                "request": request,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Per-policy friction counters
+#[derive(Default)]
+struct FrictionCounter {
+    friction: PolicyFriction,
+    users: HashSet<String>,
+}
+
+/// Block Page Service
+pub struct BlockPageService {
+    /// Page renderer
+    renderer: BlockPageRenderer,
+    
+    /// Public URL prefix of this service (e.g. https://block.example.com)
+    public_url: String,
+    
+    /// Tenant branding
+    branding: DashMap<String, TenantBranding>,
+    
+    /// Recorded blocks by ID
+    blocks: DashMap<String, BlockEvent>,
+    
+    /// Exception requests by ID
+    exceptions: DashMap<String, ExceptionRequest>,
+    
+    /// Friction counters by (tenant, policy)
+    friction: DashMap<(String, String), FrictionCounter>,
+    
+    /// Ticket/approval sink
+    sink: Option<Arc<dyn ExceptionSink>>,
+    
+    /// Lifetime of approved exceptions
+    exception_ttl: Duration,
+    
+    /// ID generation
+    id_hasher: RandomState,
+    id_counter: AtomicU64,
+}
+
+impl BlockPageService {
+    /// Create service; `public_url` is the externally reachable base URL
+    pub fn new(public_url: impl Into<String>) -> Self {
+        Self {
+            renderer: BlockPageRenderer::default(),
+            public_url: public_url.into().trim_end_matches('/').to_string(),
+            branding: DashMap::new(),
+            blocks: DashMap::new(),
+            exceptions: DashMap::new(),
+            friction: DashMap::new(),
+            sink: None,
+            exception_ttl: Duration::days(7),
+            id_hasher: RandomState::new(),
+            id_counter: AtomicU64::new(0),
+        }
+    }
+    
+    /// Send exception requests to a ticketing/approval system
+    pub fn with_exception_sink(mut self, sink: Arc<dyn ExceptionSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+    
+    /// Set how long approved exceptions last
+    pub fn with_exception_ttl(mut self, ttl: Duration) -> Self {
+        self.exception_ttl = ttl;
+        self
+    }
+    
+    /// Set tenant branding
+    pub fn set_branding(&self, tenant_id: &str, branding: TenantBranding) {
+        info!("Updated block page branding for tenant {}", tenant_id);
+        self.branding.insert(tenant_id.to_string(), branding);
+    }
+    
+    /// Get tenant branding (default if unset)
+    pub fn branding(&self, tenant_id: &str) -> TenantBranding {
+        self.branding.get(tenant_id)
+            .map(|b| b.clone())
+            .unwrap_or_default()
+    }
+    
+    /// Record a block; returns the event whose page explains it
+    pub fn record_block(&self, context: BlockContext) -> BlockEvent {
+        let event = BlockEvent {
+            id: self.next_id("blk"),
+            context,
+            occurred_at: Utc::now(),
+        };
+        
+        let mut counter = self.counter(&event);
+        counter.friction.blocks += 1;
+        if let Some(user) = &event.context.user_id {
+            counter.users.insert(user.clone());
+        }
+        drop(counter);
+        
+        debug!(
+            "Block {} recorded: tenant={} policy={} resource={}",
+            event.id, event.context.tenant_id, event.context.policy_id, event.context.resource
+        );
+        self.blocks.insert(event.id.clone(), event.clone());
+        event
+    }
+    
+    /// Public URL of a block's page
+    pub fn page_url(&self, block_id: &str) -> String {
+        format!("{}/block/{}", self.public_url, block_id)
+    }
+    
+    /// Get a recorded block
+    pub fn get_block(&self, block_id: &str) -> Option<BlockEvent> {
+        self.blocks.get(block_id).map(|b| b.clone())
+    }
+    
+    /// Render a block page and count the view
+    pub fn render(&self, block_id: &str) -> Option<String> {
+        let event = self.get_block(block_id)?;
+        self.record_interaction(block_id, PageInteraction::Viewed);
+        Some(self.renderer.render(&event, &self.branding(&event.context.tenant_id)))
+    }
+    
+    /// Record a click-through/interaction on a block page
+    pub fn record_interaction(&self, block_id: &str, interaction: PageInteraction) -> bool {
+        let Some(event) = self.get_block(block_id) else {
+            return false;
+        };
+        
+        let mut counter = self.counter(&event);
+        let friction = &mut counter.friction;
+        match interaction {
+            PageInteraction::Viewed => friction.page_views += 1,
+            PageInteraction::ClickedSupport => friction.support_clicks += 1,
+            PageInteraction::ClickedBack => friction.back_clicks += 1,
+            PageInteraction::RequestedException => friction.exception_requests += 1,
+        }
+        true
+    }
+    
+    /// Submit an exception request for a block
+    pub async fn request_exception(
+        &self,
+        block_id: &str,
+        justification: &str,
+    ) -> Result<ExceptionRequest> {
+        let block = self.get_block(block_id)
+            .ok_or_else(|| L7Error::BlockPageError(format!("Unknown block: {}", block_id)))?;
+        
+        if !self.branding(&block.context.tenant_id).allow_exception_requests {
+            return Err(L7Error::BlockPageError(
+                "Exception requests are disabled for this tenant".to_string(),
+            ));
+        }
+        
+        let justification = justification.trim();
+        if justification.is_empty() {
+            return Err(L7Error::BlockPageError("Justification is required".to_string()));
+        }
+        if justification.len() > MAX_JUSTIFICATION_LEN {
+            return Err(L7Error::BlockPageError(format!(
+                "Justification exceeds {} characters",
+                MAX_JUSTIFICATION_LEN
+            )));
+        }
+        
+        let mut request = ExceptionRequest {
+            id: self.next_id("exc"),
+            requested_by: block.context.user_id.clone(),
+            block,
+            justification: justification.to_string(),
+            status: ExceptionStatus::Pending,
+            ticket_ref: None,
+            created_at: Utc::now(),
+            decided_by: None,
+            decided_at: None,
+            expires_at: None,
+        };
+        
+        if let Some(sink) = &self.sink {
+            match sink.open(&request).await {
+                Ok(ticket) => request.ticket_ref = Some(ticket),
+                // Keep the request; an approver can still act on it here
+                Err(e) => warn!("Failed to open ticket for exception {}: {}", request.id, e),
+            }
+        }
+        
+        self.record_interaction(block_id, PageInteraction::RequestedException);
+        info!(
+            "Exception {} requested for policy {} on {}",
+            request.id, request.block.context.policy_id, request.block.context.resource
+        );
+        self.exceptions.insert(request.id.clone(), request.clone());
+        Ok(request)
+    }
+    
+    /// Approve or deny a pending exception
+    pub async fn decide(
+        &self,
+        exception_id: &str,
+        approve: bool,
+        decided_by: &str,
+    ) -> Result<ExceptionRequest> {
+        let request = {
+            let mut entry = self.exceptions.get_mut(exception_id)
+                .ok_or_else(|| L7Error::BlockPageError(format!("Unknown exception: {}", exception_id)))?;
+            
+            if entry.status != ExceptionStatus::Pending {
+                return Err(L7Error::BlockPageError(format!(
+                    "Exception {} already decided",
+                    exception_id
+                )));
+            }
+            
+            let now = Utc::now();
+            entry.decided_by = Some(decided_by.to_string());
+            entry.decided_at = Some(now);
+            if approve {
+                entry.status = ExceptionStatus::Approved;
+                entry.expires_at = Some(now + self.exception_ttl);
+            } else {
+                entry.status = ExceptionStatus::Denied;
+            }
+            entry.clone()
+        };
+        
+        if approve {
+            self.counter(&request.block).friction.exceptions_approved += 1;
+        }
+        
+        info!(
+            "Exception {} {} by {}",
+            exception_id,
+            if approve { "approved" } else { "denied" },
+            decided_by
+        );
+        
+        if let Some(sink) = &self.sink {
+            if let Err(e) = sink.decided(&request).await {
+                warn!("Failed to report decision for exception {}: {}", exception_id, e);
+            }
+        }
+        
+        Ok(request)
+    }
+    
+    /// Get an exception request
+    pub fn get_exception(&self, exception_id: &str) -> Option<ExceptionRequest> {
+        self.exceptions.get(exception_id).map(|e| e.clone())
+    }
+    
+    /// Whether an approved, unexpired exception covers this user, policy and resource
+    pub fn has_active_exception(
+        &self,
+        tenant_id: &str,
+        user_id: &str,
+        policy_id: &str,
+        resource: &str,
+    ) -> bool {
+        let now = Utc::now();
+        self.exceptions.iter().any(|e| {
+            let ctx = &e.block.context;
+            e.status == ExceptionStatus::Approved
+                && e.expires_at.is_some_and(|exp| exp > now)
+                && ctx.tenant_id == tenant_id
+                && ctx.user_id.as_deref() == Some(user_id)
+                && ctx.policy_id == policy_id
+                && ctx.resource == resource
+        })
+    }
+    
+    /// Policies ranked by the friction they cause for a tenant
+    pub fn friction_report(&self, tenant_id: &str) -> Vec<PolicyFriction> {
+        let mut report: Vec<PolicyFriction> = self.friction.iter()
+            .filter(|entry| entry.key().0 == tenant_id)
+            .map(|entry| {
+                let mut friction = entry.friction.clone();
+                friction.distinct_users = entry.users.len();
+                friction
+            })
+            .collect();
+        
+        report.sort_by(|a, b| b.score().cmp(&a.score()).then(b.blocks.cmp(&a.blocks)));
+        report
+    }
+    
+    /// Drop blocks older than `max_age` that have no pending exception
+    pub fn purge(&self, max_age: Duration) -> usize {
+        let cutoff = Utc::now() - max_age;
+        let pending: HashSet<String> = self.exceptions.iter()
+            .filter(|e| e.status == ExceptionStatus::Pending)
+            .map(|e| e.block.id.clone())
+            .collect();
+        
+        let before = self.blocks.len();
+        self.blocks.retain(|id, b| b.occurred_at >= cutoff || pending.contains(id));
+        self.exceptions.retain(|_, e| {
+            e.status == ExceptionStatus::Pending
+                || e.expires_at.is_some_and(|exp| exp >= cutoff)
+                || e.decided_at.is_some_and(|at| at >= cutoff)
+        });
+        before - self.blocks.len()
+    }
+    
+    fn counter(&self, event: &BlockEvent) -> dashmap::mapref::one::RefMut<'_, (String, String), FrictionCounter> {
+        let ctx = &event.context;
+        let mut counter = self.friction
+            .entry((ctx.tenant_id.clone(), ctx.policy_id.clone()))
+            .or_default();
+        if counter.friction.policy_id.is_empty() {
+            counter.friction.policy_id = ctx.policy_id.clone();
+            counter.friction.source = Some(ctx.source);
+        }
+        // Keep the latest display name
+        counter.friction.policy_name = ctx.policy_name.clone();
+        counter
+    }
+    
+    fn next_id(&self, prefix: &str) -> String {
+        let mut hasher = self.id_hasher.build_hasher();
+        hasher.write_u64(self.id_counter.fetch_add(1, Ordering::Relaxed));
+        hasher.write_i64(Utc::now().timestamp_nanos_opt().unwrap_or_default());
+        format!("{}_{:016x}", prefix, hasher.finish())
+    }
+    
+    /// Create Axum router
+    pub fn router(service: Arc<Self>) -> Router {
+        Router::new()
+            .route("/blocks", post(record_block_handler))
+            .route("/block/:id", get(page_handler))
+            .route("/block/:id/exception", post(exception_handler))
+            .route("/block/:id/click", get(click_handler).post(click_handler))
+            .route("/exceptions/:id", get(get_exception_handler))
+            .route("/exceptions/:id/decision", post(decision_handler))
+            .route("/tenants/:tenant/friction", get(friction_handler))
+            .route("/tenants/:tenant/branding", put(branding_handler))
+            .with_state(service)
+    }
+}
+
+/// Recorded block response
+#[derive(Debug, Serialize)]
+struct RecordBlockResponse {
+    id: String,
+    page_url: String,
+}
+
+/// Exception form body
+#[derive(Debug, Deserialize)]
+struct ExceptionForm {
+    justification: String,
+}
+
+/// Click query
+#[derive(Debug, Deserialize)]
+struct ClickQuery {
+    target: String,
+    to: Option<String>,
+}
+
+/// Decision body
+#[derive(Debug, Deserialize)]
+struct DecisionRequest {
+    approve: bool,
+    decided_by: String,
+}
+
+fn bad_request(e: L7Error) -> Response {
+    (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+}
+
+/// Record block handler
+async fn record_block_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Json(context): Json<BlockContext>,
+) -> Json<RecordBlockResponse> {
+    let event = service.record_block(context);
+    Json(RecordBlockResponse {
+        page_url: service.page_url(&event.id),
+        id: event.id,
+    })
+}
+
+/// Block page handler
+async fn page_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(id): Path<String>,
+) -> Response {
+    match service.render(&id) {
+        Some(html) => (StatusCode::FORBIDDEN, Html(html)).into_response(),
+        None => (StatusCode::NOT_FOUND, "Unknown block reference").into_response(),
+    }
+}
+
+/// Exception request handler (HTML form post)
+async fn exception_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(id): Path<String>,
+    Form(form): Form<ExceptionForm>,
+) -> Response {
+    match service.request_exception(&id, &form.justification).await {
+        Ok(request) => {
+            let reference = request.ticket_ref.as_deref().unwrap_or(&request.id);
+            Html(format!(
+                "<!DOCTYPE html><html><body><h1>Request submitted</h1>\
+                 <p>Your exception request was sent for review. Reference: {}</p></body></html>",
+                reference.replace(['<', '>', '&', '"'], "")
+            ))
+            .into_response()
+        }
+        Err(e) => bad_request(e),
+    }
+}
+
+/// Click-through handler
+async fn click_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(id): Path<String>,
+    Query(query): Query<ClickQuery>,
+) -> Response {
+    let interaction = match query.target.as_str() {
+        "support" => PageInteraction::ClickedSupport,
+        "back" => PageInteraction::ClickedBack,
+        _ => return (StatusCode::BAD_REQUEST, "Unknown click target").into_response(),
+    };
+    
+    if !service.record_interaction(&id, interaction) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    
+    // Only redirect to the tenant's configured support URL
+    if let (Some(to), Some(block)) = (query.to, service.get_block(&id)) {
+        let branding = service.branding(&block.context.tenant_id);
+        if branding.support_url.as_deref() == Some(to.as_str()) {
+            return Redirect::to(&to).into_response();
+        }
+    }
+    
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Get exception handler
+async fn get_exception_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(id): Path<String>,
+) -> Response {
+    match service.get_exception(&id) {
+        Some(request) => Json(request).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Exception decision handler
+async fn decision_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(id): Path<String>,
+    Json(decision): Json<DecisionRequest>,
+) -> Response {
+    match service.decide(&id, decision.approve, &decision.decided_by).await {
+        Ok(request) => Json(request).into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// Friction report handler
+async fn friction_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(tenant): Path<String>,
+) -> Json<Vec<PolicyFriction>> {
+    Json(service.friction_report(&tenant))
+}
+
+/// Branding handler
+async fn branding_handler(
+    State(service): State<Arc<BlockPageService>>,
+    Path(tenant): Path<String>,
+    Json(branding): Json<TenantBranding>,
+) -> StatusCode {
+    service.set_branding(&tenant, branding);
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockpage::BlockSource;
+    
+    fn context(user: &str) -> BlockContext {
+        BlockContext {
+            tenant_id: "acme".to_string(),
+            user_id: Some(user.to_string()),
+            source: BlockSource::UrlFilter,
+            policy_id: "category:gambling".to_string(),
+            policy_name: "Block gambling".to_string(),
+            reason: "Category 'gambling' is blocked by policy".to_string(),
+            resource: "casino.example".to_string(),
+            category: Some("gambling".to_string()),
+        }
+    }
+    
+    #[test]
+    fn test_render_branded_and_escaped() {
+        let service = BlockPageService::new("https://block.example.com");
+        service.set_branding("acme", TenantBranding {
+            company_name: "Acme <Corp>".to_string(),
+            ..Default::default()
+        });
+        
+        let mut ctx = context("alice");
+        ctx.resource = "casino.example/<script>".to_string();
+        let event = service.record_block(ctx);
+        let html = service.render(&event.id).unwrap();
+        
+        assert!(html.contains("Acme &lt;Corp&gt;"));
+        assert!(html.contains("Block gambling"));
+        assert!(html.contains("casino.example/&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("/block/") && html.contains("/exception"));
+    }
+    
+    #[tokio::test]
+    async fn test_exception_workflow_and_friction() {
+        let service = BlockPageService::new("https://block.example.com");
+        let first = service.record_block(context("alice"));
+        service.record_block(context("bob"));
+        service.render(&first.id);
+        service.record_interaction(&first.id, PageInteraction::ClickedSupport);
+        
+        assert!(service.request_exception(&first.id, "  ").await.is_err());
+        let request = service.request_exception(&first.id, "Vendor research").await.unwrap();
+        assert_eq!(request.status, ExceptionStatus::Pending);
+        assert!(!service.has_active_exception("acme", "alice", "category:gambling", "casino.example"));
+        
+        service.decide(&request.id, true, "admin").await.unwrap();
+        assert!(service.decide(&request.id, false, "admin").await.is_err());
+        assert!(service.has_active_exception("acme", "alice", "category:gambling", "casino.example"));
+        assert!(!service.has_active_exception("acme", "bob", "category:gambling", "casino.example"));
+        
+        let report = service.friction_report("acme");
+        assert_eq!(report.len(), 1);
+        let friction = &report[0];
+        assert_eq!(friction.blocks, 2);
+        assert_eq!(friction.page_views, 1);
+        assert_eq!(friction.support_clicks, 1);
+        assert_eq!(friction.exception_requests, 1);
+        assert_eq!(friction.exceptions_approved, 1);
+        assert_eq!(friction.distinct_users, 2);
+    }
+}
@@ -8,6 +8,7 @@
 //! - **SWG**: URL filtering and categorization
 //! - **CASB**: SaaS application connectors
 //! - **DLP**: Data Loss Prevention inspection
+//! - **Block Pages**: Tenant-branded block notifications and exception requests
 //!
 //! ## Performance Targets
 //!
@@ -19,10 +20,12 @@ pub mod authz;
 pub mod swg;
 pub mod casb;
 pub mod dlp;
+pub mod blockpage;
 
 pub use authz::PolicyEngine;
 pub use swg::UrlFilterService;
 pub use casb::CasbService;
+pub use blockpage::BlockPageService;
 
 use thiserror::Error;
 
@@ -41,6 +44,9 @@ pub enum L7Error {
     #[error("DLP scan error: {0}")]
     DlpError(String),
 
+    #[error("Block page error: {0}")]
+    BlockPageError(String),
+
     #[error("gRPC error: {0}")]
     GrpcError(#[from] tonic::Status),
 
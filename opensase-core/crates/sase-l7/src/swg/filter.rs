@@ -4,6 +4,7 @@
 
 use crate::swg::{Category, CategoryDatabase, BlocklistManager};
 use crate::authz::PolicyStore;
+use crate::blockpage::{BlockContext, BlockPageService, BlockSource};
 use crate::Result;
 use axum::{
     extract::{Query, State},
@@ -49,6 +50,9 @@ pub enum UrlCheckResponse {
     Block {
         reason: String,
         category: String,
+        /// Block page explaining the policy
+        #[serde(skip_serializing_if = "Option::is_none")]
+        block_page: Option<String>,
     },
     
     /// Warn user
@@ -87,6 +91,9 @@ pub struct UrlFilterService {
     
     /// Request counter
     request_count: std::sync::atomic::AtomicU64,
+    
+    /// Block pages and exceptions
+    block_pages: Option<Arc<BlockPageService>>,
 }
 
 impl UrlFilterService {
@@ -101,9 +108,16 @@ impl UrlFilterService {
             category_db,
             policy_store,
             request_count: std::sync::atomic::AtomicU64::new(0),
+            block_pages: None,
         }
     }
     
+    /// Link blocks to tenant-branded block pages
+    pub fn with_block_pages(mut self, block_pages: Arc<BlockPageService>) -> Self {
+        self.block_pages = Some(block_pages);
+        self
+    }
+    
     /// Check URL
     pub async fn check_url(&self, request: UrlCheckRequest) -> UrlCheckResponse {
         self.request_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -116,10 +130,13 @@ impl UrlFilterService {
             // Confirm with exact lookup (bloom filters have false positives)
             if let Some(reason) = self.blocklist.is_blocked_exact(&domain).await {
                 warn!("Blocked domain: {} - {}", domain, reason);
-                return UrlCheckResponse::Block {
-                    reason: reason.clone(),
-                    category: "blocked".to_string(),
-                };
+                return self.block(
+                    &request,
+                    &domain,
+                    ("blocklist", "Threat blocklist"),
+                    reason,
+                    "blocked",
+                );
             }
         }
         
@@ -128,12 +145,15 @@ impl UrlFilterService {
             .unwrap_or(Category::Unknown);
         
         // 3. Check blocked categories
-        if self.is_blocked_category(&category) {
+        if self.is_blocked_category(&category) && !self.has_exception(&request, &category, &domain) {
             warn!("Blocked category: {} for {}", category.as_str(), domain);
-            return UrlCheckResponse::Block {
-                reason: format!("Category '{}' is blocked by policy", category.as_str()),
-                category: category.as_str().to_string(),
-            };
+            return self.block(
+                &request,
+                &domain,
+                (&category_policy_id(&category), &format!("Blocked category: {}", category.as_str())),
+                format!("Category '{}' is blocked by policy", category.as_str()),
+                category.as_str(),
+            );
         }
         
         // 4. Check if isolation required
@@ -161,6 +181,49 @@ impl UrlFilterService {
         }
     }
     
+    /// Build a block response, recording it for the block page
+    fn block(
+        &self,
+        request: &UrlCheckRequest,
+        domain: &str,
+        (policy_id, policy_name): (&str, &str),
+        reason: String,
+        category: &str,
+    ) -> UrlCheckResponse {
+        let block_page = self.block_pages.as_ref().map(|pages| {
+            let event = pages.record_block(BlockContext {
+                tenant_id: request.tenant_id.clone().unwrap_or_default(),
+                user_id: request.user_id.clone(),
+                source: BlockSource::UrlFilter,
+                policy_id: policy_id.to_string(),
+                policy_name: policy_name.to_string(),
+                reason: reason.clone(),
+                resource: domain.to_string(),
+                category: Some(category.to_string()),
+            });
+            pages.page_url(&event.id)
+        });
+        
+        UrlCheckResponse::Block {
+            reason,
+            category: category.to_string(),
+            block_page,
+        }
+    }
+    
+    /// Check for an approved exception to a category block
+    fn has_exception(&self, request: &UrlCheckRequest, category: &Category, domain: &str) -> bool {
+        let (Some(pages), Some(user)) = (&self.block_pages, &request.user_id) else {
+            return false;
+        };
+        let tenant = request.tenant_id.as_deref().unwrap_or_default();
+        let allowed = pages.has_active_exception(tenant, user, &category_policy_id(category), domain);
+        if allowed {
+            info!("Exception allows {} for {}", domain, user);
+        }
+        allowed
+    }
+    
     /// Check if category is blocked
     fn is_blocked_category(&self, category: &Category) -> bool {
         matches!(
@@ -210,6 +273,11 @@ impl UrlFilterService {
     }
 }
 
+/// Policy ID for a category block
+fn category_policy_id(category: &Category) -> String {
+    format!("category:{}", category.as_str())
+}
+
 /// Extract domain from host
 fn extract_domain(host: &str) -> String {
     // Remove port if present
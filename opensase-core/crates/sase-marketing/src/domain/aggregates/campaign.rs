@@ -11,7 +11,7 @@ pub struct Campaign {
     stats: CampaignStats, created_at: DateTime<Utc>, events: Vec<DomainEvent>,
 }
 
-#[derive(Clone, Debug, Default)] pub struct CampaignStats { pub sent: u64, pub delivered: u64, pub opened: u64, pub clicked: u64, pub bounced: u64, pub unsubscribed: u64 }
+#[derive(Clone, Debug, Default)] pub struct CampaignStats { pub sent: u64, pub delivered: u64, pub opened: u64, pub clicked: u64, pub bounced: u64, pub unsubscribed: u64, pub landing_visits: u64, pub conversions: u64 }
 #[derive(Clone, Debug, Default, PartialEq, Eq)] pub enum CampaignStatus { #[default] Draft, Scheduled, Sending, Sent, Paused, Cancelled }
 
 impl Campaign {
@@ -44,6 +44,9 @@ impl Campaign {
     
     pub fn cancel(&mut self) { self.status = CampaignStatus::Cancelled; }
     
+    pub fn record_landing_visit(&mut self) { self.stats.landing_visits += 1; }
+    pub fn record_conversion(&mut self) { self.stats.conversions += 1; }
+    
     pub fn take_events(&mut self) -> Vec<DomainEvent> { std::mem::take(&mut self.events) }
     fn raise_event(&mut self, e: DomainEvent) { self.events.push(e); }
 }
@@ -0,0 +1,150 @@
+//! Landing Page Aggregate
+use chrono::{DateTime, Utc};
+use crate::domain::value_objects::PageSection;
+use crate::domain::events::{DomainEvent, LandingPageEvent};
+
+#[derive(Clone, Debug)]
+pub struct LandingPage {
+    id: String, tenant_id: String, name: String, slug: String, status: LandingPageStatus,
+    campaign_id: Option<String>, draft: PageContent, versions: Vec<PageVersion>, published_version: Option<u32>,
+    stats: LandingPageStats, created_at: DateTime<Utc>, updated_at: DateTime<Utc>, events: Vec<DomainEvent>,
+}
+
+#[derive(Clone, Debug, Default)] pub struct PageContent { pub title: String, pub meta_description: Option<String>, pub sections: Vec<PageSection> }
+
+impl PageContent {
+    /// Form IDs embedded in the content
+    pub fn forms(&self) -> Vec<String> {
+        self.sections.iter().flat_map(|s| s.blocks.iter()).filter_map(|b| match b {
+            crate::domain::value_objects::PageBlock::Form { form_id } => Some(form_id.clone()),
+            _ => None,
+        }).collect()
+    }
+}
+#[derive(Clone, Debug)] pub struct PageVersion { pub version: u32, pub content: PageContent, pub published_at: DateTime<Utc> }
+#[derive(Clone, Debug, Default)] pub struct LandingPageStats { pub visits: u64, pub unique_visitors: u64, pub conversions: u64 }
+#[derive(Clone, Debug, Default, PartialEq, Eq)] pub enum LandingPageStatus { #[default] Draft, Published, Unpublished, Archived }
+
+impl LandingPage {
+    pub fn create(tenant_id: impl Into<String>, name: impl Into<String>, slug: impl Into<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(), tenant_id: tenant_id.into(), name: name.into(), slug: normalize_slug(&slug.into()),
+            status: LandingPageStatus::Draft, campaign_id: None, draft: PageContent::default(), versions: vec![], published_version: None,
+            stats: LandingPageStats::default(), created_at: Utc::now(), updated_at: Utc::now(), events: vec![],
+        }
+    }
+
+    pub fn id(&self) -> &str { &self.id }
+    pub fn tenant_id(&self) -> &str { &self.tenant_id }
+    pub fn name(&self) -> &str { &self.name }
+    pub fn slug(&self) -> &str { &self.slug }
+    pub fn status(&self) -> &LandingPageStatus { &self.status }
+    pub fn campaign_id(&self) -> Option<&str> { self.campaign_id.as_deref() }
+    pub fn draft(&self) -> &PageContent { &self.draft }
+    pub fn versions(&self) -> &[PageVersion] { &self.versions }
+    pub fn stats(&self) -> &LandingPageStats { &self.stats }
+    pub fn created_at(&self) -> DateTime<Utc> { self.created_at }
+    pub fn updated_at(&self) -> DateTime<Utc> { self.updated_at }
+
+    /// Content currently served to visitors, if published
+    pub fn live_content(&self) -> Option<&PageContent> {
+        if self.status != LandingPageStatus::Published { return None; }
+        let version = self.published_version?;
+        self.versions.iter().find(|v| v.version == version).map(|v| &v.content)
+    }
+
+    pub fn link_campaign(&mut self, campaign_id: impl Into<String>) { self.campaign_id = Some(campaign_id.into()); self.touch(); }
+    pub fn set_title(&mut self, title: impl Into<String>, meta_description: Option<String>) { self.draft.title = title.into(); self.draft.meta_description = meta_description; self.touch(); }
+    pub fn add_section(&mut self, section: PageSection) { self.draft.sections.push(section); self.touch(); }
+    pub fn remove_section(&mut self, section_id: &str) { self.draft.sections.retain(|s| s.id != section_id); self.touch(); }
+
+    /// Form IDs embedded in the draft
+    pub fn embedded_forms(&self) -> Vec<String> { self.draft.forms() }
+
+    /// Snapshot the draft as a new immutable version and serve it
+    pub fn publish(&mut self) -> Result<u32, LandingPageError> {
+        if self.status == LandingPageStatus::Archived { return Err(LandingPageError::Archived); }
+        if self.draft.sections.is_empty() { return Err(LandingPageError::NoContent); }
+        let version = self.versions.last().map(|v| v.version + 1).unwrap_or(1);
+        self.versions.push(PageVersion { version, content: self.draft.clone(), published_at: Utc::now() });
+        self.published_version = Some(version);
+        self.status = LandingPageStatus::Published;
+        self.touch();
+        self.raise_event(DomainEvent::LandingPage(LandingPageEvent::Published { page_id: self.id.clone(), version }));
+        Ok(version)
+    }
+
+    /// Re-publish a previous version without touching the draft
+    pub fn rollback(&mut self, version: u32) -> Result<(), LandingPageError> {
+        if !self.versions.iter().any(|v| v.version == version) { return Err(LandingPageError::VersionNotFound(version)); }
+        self.published_version = Some(version);
+        self.status = LandingPageStatus::Published;
+        self.touch();
+        self.raise_event(DomainEvent::LandingPage(LandingPageEvent::Published { page_id: self.id.clone(), version }));
+        Ok(())
+    }
+
+    pub fn unpublish(&mut self) -> Result<(), LandingPageError> {
+        if self.status != LandingPageStatus::Published { return Err(LandingPageError::NotPublished); }
+        self.status = LandingPageStatus::Unpublished;
+        self.touch();
+        self.raise_event(DomainEvent::LandingPage(LandingPageEvent::Unpublished { page_id: self.id.clone() }));
+        Ok(())
+    }
+
+    pub fn archive(&mut self) { self.status = LandingPageStatus::Archived; self.touch(); }
+
+    pub fn record_visit(&mut self, visit_id: &str, first_visit: bool) {
+        self.stats.visits += 1;
+        if first_visit { self.stats.unique_visitors += 1; }
+        self.raise_event(DomainEvent::LandingPage(LandingPageEvent::Visited { page_id: self.id.clone(), visit_id: visit_id.to_string() }));
+    }
+
+    pub fn record_conversion(&mut self, visit_id: &str, contact_id: &str) {
+        self.stats.conversions += 1;
+        self.raise_event(DomainEvent::LandingPage(LandingPageEvent::Converted { page_id: self.id.clone(), visit_id: visit_id.to_string(), contact_id: contact_id.to_string() }));
+    }
+
+    pub fn conversion_rate(&self) -> f64 {
+        if self.stats.visits == 0 { 0.0 } else { self.stats.conversions as f64 / self.stats.visits as f64 }
+    }
+
+    pub fn take_events(&mut self) -> Vec<DomainEvent> { std::mem::take(&mut self.events) }
+    fn raise_event(&mut self, e: DomainEvent) { self.events.push(e); }
+    fn touch(&mut self) { self.updated_at = Utc::now(); }
+}
+
+fn normalize_slug(slug: &str) -> String {
+    slug.trim_matches('/').to_lowercase().chars().map(|c| if c.is_ascii_alphanumeric() || c == '/' { c } else { '-' }).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)] pub enum LandingPageError { NoContent, NotPublished, Archived, VersionNotFound(u32) }
+impl std::error::Error for LandingPageError {}
+impl std::fmt::Display for LandingPageError { fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "Landing page error: {:?}", self) } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::PageBlock;
+
+    fn section(text: &str) -> PageSection { PageSection { id: uuid::Uuid::new_v4().to_string(), blocks: vec![PageBlock::Heading { text: text.into(), level: 1 }], background: None } }
+
+    #[test]
+    fn test_publish_versions_and_rollback() {
+        let mut page = LandingPage::create("tenant-1", "Spring Promo", "Spring Promo");
+        assert_eq!(page.slug(), "spring-promo");
+        assert_eq!(page.publish(), Err(LandingPageError::NoContent));
+
+        page.add_section(section("v1"));
+        assert_eq!(page.publish().unwrap(), 1);
+        page.add_section(section("v2"));
+        assert_eq!(page.publish().unwrap(), 2);
+        assert_eq!(page.live_content().unwrap().sections.len(), 2);
+
+        page.rollback(1).unwrap();
+        assert_eq!(page.live_content().unwrap().sections.len(), 1);
+
+        page.unpublish().unwrap();
+        assert!(page.live_content().is_none());
+    }
+}
@@ -1,5 +1,7 @@
 //! Aggregates
 pub mod campaign;
 pub mod automation;
+pub mod landing_page;
 pub use campaign::{Campaign, CampaignError, CampaignStatus, CampaignStats};
 pub use automation::{Automation, AutomationStatus, AutomationTrigger, AutomationStep, StepType};
+pub use landing_page::{LandingPage, LandingPageError, LandingPageStatus, LandingPageStats, PageContent, PageVersion};
@@ -1,9 +1,12 @@
 //! Marketing events
 #[derive(Clone, Debug)]
-pub enum DomainEvent { Campaign(CampaignEvent), Automation(AutomationEvent) }
+pub enum DomainEvent { Campaign(CampaignEvent), Automation(AutomationEvent), LandingPage(LandingPageEvent) }
 
 #[derive(Clone, Debug)]
 pub enum CampaignEvent { Created { campaign_id: String }, Sent { campaign_id: String, recipients: u64 }, Opened { campaign_id: String, contact_id: String } }
 
 #[derive(Clone, Debug)]
 pub enum AutomationEvent { Activated { automation_id: String }, ContactEnrolled { automation_id: String, contact_id: String }, StepCompleted { automation_id: String, step_id: String, contact_id: String } }
+
+#[derive(Clone, Debug)]
+pub enum LandingPageEvent { Published { page_id: String, version: u32 }, Unpublished { page_id: String }, Visited { page_id: String, visit_id: String }, Converted { page_id: String, visit_id: String, contact_id: String } }
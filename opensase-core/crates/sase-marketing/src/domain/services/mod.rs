@@ -0,0 +1,422 @@
+//! Domain services
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use crate::domain::aggregates::{Campaign, LandingPage, PageContent};
+use crate::domain::value_objects::{PageBlock, UtmParams};
+use crate::domain::events::DomainEvent;
+use sase_forms::{FieldType, Form, FormSubmission};
+use sase_forms::domain::{FieldResponse, FormStatus};
+
+/// Attribution carried from a landing page visit into a contact record
+#[derive(Clone, Debug)]
+pub struct Attribution {
+    pub page_id: String,
+    pub campaign_id: Option<String>,
+    pub utm: UtmParams,
+    pub referrer: Option<String>,
+    pub first_touch_at: DateTime<Utc>,
+    pub converted_at: DateTime<Utc>,
+}
+
+/// Port into the contact store (CRM); returns the contact ID
+pub trait ContactSink: Send + Sync {
+    fn upsert_with_attribution(&self, tenant_id: &str, email: &str, attribution: &Attribution) -> String;
+}
+
+/// Custom domain mapped to a tenant's landing pages
+#[derive(Clone, Debug)]
+pub struct CustomDomain { pub host: String, pub tenant_id: String, pub verification_token: String, pub verified: bool }
+
+/// A single landing page visit
+#[derive(Clone, Debug)]
+pub struct PageVisit {
+    pub id: String, pub tenant_id: String, pub page_id: String, pub visitor_id: String,
+    pub utm: UtmParams, pub referrer: Option<String>, pub visited_at: DateTime<Utc>, pub converted: bool,
+    /// Forms rendered on the page served; only these accept submissions for the visit
+    pub form_ids: Vec<String>,
+}
+
+/// Visits and first touches older than this are dropped
+const VISIT_RETENTION_DAYS: i64 = 90;
+
+/// First and latest visit of one visitor to one page
+#[derive(Clone, Copy, Debug)]
+struct Touch { first: DateTime<Utc>, last: DateTime<Utc> }
+
+/// Rendered response for a hosted page
+#[derive(Clone, Debug)]
+pub struct HostedPage { pub page_id: String, pub visit_id: String, pub html: String }
+
+/// Serves published landing pages per tenant and tracks visits and conversions
+pub struct LandingPageHost {
+    base_domain: String,
+    pages: DashMap<String, LandingPage>,
+    routes: DashMap<(String, String), String>,
+    domains: DashMap<String, CustomDomain>,
+    /// Form by ID with its owning tenant
+    forms: DashMap<String, (String, Form)>,
+    campaigns: DashMap<String, Campaign>,
+    visits: DashMap<String, PageVisit>,
+    /// Keyed by (page, visitor)
+    touches: DashMap<(String, String), Touch>,
+    retention: Duration,
+    last_purge: std::sync::Mutex<DateTime<Utc>>,
+    submissions: DashMap<String, FormSubmission>,
+    contacts: Box<dyn ContactSink>,
+    events: std::sync::Mutex<Vec<DomainEvent>>,
+}
+
+impl LandingPageHost {
+    pub fn new(base_domain: impl Into<String>, contacts: Box<dyn ContactSink>) -> Self {
+        Self {
+            base_domain: base_domain.into().to_lowercase(), pages: DashMap::new(), routes: DashMap::new(), domains: DashMap::new(),
+            forms: DashMap::new(), campaigns: DashMap::new(), visits: DashMap::new(), touches: DashMap::new(),
+            retention: Duration::days(VISIT_RETENTION_DAYS), last_purge: std::sync::Mutex::new(Utc::now()),
+            submissions: DashMap::new(), contacts, events: std::sync::Mutex::new(vec![]),
+        }
+    }
+
+    /// Keep visits and first-touch attribution for this long after the visitor was last seen
+    pub fn with_retention(mut self, retention: Duration) -> Self { self.retention = retention; self }
+
+    pub fn add_page(&self, page: LandingPage) {
+        self.routes.insert((page.tenant_id().to_string(), page.slug().to_string()), page.id().to_string());
+        self.pages.insert(page.id().to_string(), page);
+    }
+
+    /// Make a tenant's form embeddable on that tenant's pages
+    pub fn add_form(&self, tenant_id: &str, form: Form) { self.forms.insert(form.id().to_string(), (tenant_id.to_string(), form)); }
+    pub fn add_campaign(&self, campaign: Campaign) { self.campaigns.insert(campaign.id().to_string(), campaign); }
+    pub fn page(&self, page_id: &str) -> Option<LandingPage> { self.pages.get(page_id).map(|p| p.clone()) }
+    pub fn campaign(&self, campaign_id: &str) -> Option<Campaign> { self.campaigns.get(campaign_id).map(|c| c.clone()) }
+    pub fn visit(&self, visit_id: &str) -> Option<PageVisit> { self.visits.get(visit_id).map(|v| v.clone()) }
+
+    /// Apply a mutation to a hosted page (publish, unpublish, edit)
+    pub fn update_page<R>(&self, page_id: &str, f: impl FnOnce(&mut LandingPage) -> R) -> Result<R, HostingError> {
+        let mut page = self.pages.get_mut(page_id).ok_or(HostingError::PageNotFound)?;
+        let result = f(&mut page);
+        let events = page.take_events();
+        drop(page);
+        self.events.lock().unwrap().extend(events);
+        Ok(result)
+    }
+
+    /// Register a tenant custom domain; it is served once verified via DNS TXT
+    pub fn add_custom_domain(&self, tenant_id: &str, host: &str) -> CustomDomain {
+        let domain = CustomDomain { host: host.to_lowercase(), tenant_id: tenant_id.to_string(), verification_token: format!("opensase-verify={}", uuid::Uuid::new_v4().simple()), verified: false };
+        self.domains.insert(domain.host.clone(), domain.clone());
+        domain
+    }
+
+    /// Verify a custom domain against its published TXT records
+    pub fn verify_custom_domain(&self, host: &str, txt_records: &[String]) -> Result<(), HostingError> {
+        let mut domain = self.domains.get_mut(&host.to_lowercase()).ok_or(HostingError::UnknownHost)?;
+        if !txt_records.iter().any(|r| r.trim_matches('"') == domain.verification_token) { return Err(HostingError::DomainNotVerified); }
+        domain.verified = true;
+        Ok(())
+    }
+
+    pub fn remove_custom_domain(&self, host: &str) { self.domains.remove(&host.to_lowercase()); }
+
+    /// Resolve the tenant for a request host: `<tenant>.<base_domain>` or a verified custom domain
+    pub fn resolve_tenant(&self, host: &str) -> Result<String, HostingError> {
+        let host = host.split(':').next().unwrap_or(host).to_lowercase();
+        if let Some(tenant) = host.strip_suffix(&format!(".{}", self.base_domain)) {
+            if !tenant.is_empty() && !tenant.contains('.') { return Ok(tenant.to_string()); }
+        }
+        let domain = self.domains.get(&host).ok_or(HostingError::UnknownHost)?;
+        if !domain.verified { return Err(HostingError::DomainNotVerified); }
+        Ok(domain.tenant_id.clone())
+    }
+
+    /// Serve a page request and record the visit
+    pub fn handle_request(&self, host: &str, path: &str, visitor_id: &str, referrer: Option<&str>) -> Result<HostedPage, HostingError> {
+        let tenant_id = self.resolve_tenant(host)?;
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let slug = path.trim_matches('/').to_lowercase();
+        let page_id = self.routes.get(&(tenant_id.clone(), slug)).map(|r| r.clone()).ok_or(HostingError::PageNotFound)?;
+
+        let (content, campaign_id) = {
+            let page = self.pages.get(&page_id).ok_or(HostingError::PageNotFound)?;
+            let content = page.live_content().cloned().ok_or(HostingError::PageNotPublished)?;
+            (content, page.campaign_id().map(|c| c.to_string()))
+        };
+
+        let now = Utc::now();
+        self.purge_if_due(now);
+
+        let form_ids = content.forms().into_iter().filter(|f| self.form_for(&tenant_id, f).is_some()).collect();
+        let visit = PageVisit {
+            id: uuid::Uuid::new_v4().to_string(), tenant_id: tenant_id.clone(), page_id: page_id.clone(), visitor_id: visitor_id.to_string(),
+            utm: UtmParams::from_query(query), referrer: referrer.map(|r| r.to_string()), visited_at: now, converted: false, form_ids,
+        };
+        let mut first_visit = false;
+        self.touches.entry((page_id.clone(), visitor_id.to_string()))
+            .and_modify(|t| t.last = now)
+            .or_insert_with(|| { first_visit = true; Touch { first: now, last: now } });
+
+        self.update_page(&page_id, |p| p.record_visit(&visit.id, first_visit))?;
+        if let Some(campaign_id) = self.attributed_campaign(campaign_id.as_deref(), &visit.utm) {
+            if let Some(mut campaign) = self.campaigns.get_mut(&campaign_id) { campaign.record_landing_visit(); }
+        }
+
+        let html = self.render(&content, &visit);
+        let visit_id = visit.id.clone();
+        self.visits.insert(visit_id.clone(), visit);
+
+        Ok(HostedPage { page_id, visit_id, html })
+    }
+
+    /// Accept an embedded form submission as a conversion for the visit
+    ///
+    /// The form must have been served on the visit's page; a visit converts once.
+    pub fn submit_form(&self, visit_id: &str, form_id: &str, email: &str, responses: Vec<FieldResponse>) -> Result<String, HostingError> {
+        let visit = {
+            let mut visit = self.visits.get_mut(visit_id).ok_or(HostingError::VisitNotFound)?;
+            self.form_for(&visit.tenant_id, form_id).ok_or(HostingError::FormNotFound)?;
+            if !visit.form_ids.iter().any(|f| f == form_id) { return Err(HostingError::FormNotOnPage); }
+            if visit.converted { return Err(HostingError::AlreadyConverted); }
+            visit.converted = true;
+            visit.clone()
+        };
+        if let Some(mut entry) = self.forms.get_mut(form_id) { entry.1.record_submission(); }
+
+        let mut submission = FormSubmission::create(form_id, responses);
+        submission.submitter_email = Some(email.to_string());
+        self.submissions.insert(submission.id.clone(), submission);
+
+        let page_campaign = self.pages.get(&visit.page_id).and_then(|p| p.campaign_id().map(|c| c.to_string()));
+        let campaign_id = self.attributed_campaign(page_campaign.as_deref(), &visit.utm);
+        let attribution = Attribution {
+            page_id: visit.page_id.clone(), campaign_id: campaign_id.clone(), utm: visit.utm.clone(), referrer: visit.referrer.clone(),
+            first_touch_at: self.first_touch(&visit), converted_at: Utc::now(),
+        };
+        let contact_id = self.contacts.upsert_with_attribution(&visit.tenant_id, email, &attribution);
+
+        self.update_page(&visit.page_id, |p| p.record_conversion(visit_id, &contact_id))?;
+        if let Some(campaign_id) = campaign_id {
+            if let Some(mut campaign) = self.campaigns.get_mut(&campaign_id) { campaign.record_conversion(); }
+        }
+
+        Ok(contact_id)
+    }
+
+    /// Drop visits and first touches not seen within the retention period
+    pub fn purge_expired(&self, now: DateTime<Utc>) {
+        let cutoff = now - self.retention;
+        self.visits.retain(|_, v| v.visited_at > cutoff);
+        self.touches.retain(|_, t| t.last > cutoff);
+        *self.last_purge.lock().unwrap() = now;
+    }
+
+    fn purge_if_due(&self, now: DateTime<Utc>) {
+        let due = now - *self.last_purge.lock().unwrap() >= Duration::hours(1);
+        if due { self.purge_expired(now); }
+    }
+
+    /// A published form owned by the tenant
+    fn form_for(&self, tenant_id: &str, form_id: &str) -> Option<Form> {
+        self.forms.get(form_id)
+            .filter(|entry| entry.0 == tenant_id && entry.1.status() == &FormStatus::Published)
+            .map(|entry| entry.1.clone())
+    }
+
+    /// Drain domain events raised by hosted pages
+    pub fn take_events(&self) -> Vec<DomainEvent> { std::mem::take(&mut *self.events.lock().unwrap()) }
+
+    /// Explicit page link wins; otherwise `utm_campaign` may name a campaign by ID
+    fn attributed_campaign(&self, page_campaign: Option<&str>, utm: &UtmParams) -> Option<String> {
+        page_campaign.map(|c| c.to_string())
+            .or_else(|| utm.campaign.as_ref().filter(|c| self.campaigns.contains_key(*c)).cloned())
+    }
+
+    /// Earliest visit from the same visitor to the same page
+    fn first_touch(&self, visit: &PageVisit) -> DateTime<Utc> {
+        self.touches.get(&(visit.page_id.clone(), visit.visitor_id.clone()))
+            .map(|t| t.first.min(visit.visited_at))
+            .unwrap_or(visit.visited_at)
+    }
+
+    fn render(&self, content: &PageContent, visit: &PageVisit) -> String {
+        let mut html = format!("<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title>", escape(&content.title));
+        if let Some(desc) = &content.meta_description { html.push_str(&format!("<meta name=\"description\" content=\"{}\">", escape(desc))); }
+        html.push_str("</head><body>");
+        for section in &content.sections {
+            match &section.background {
+                Some(bg) => html.push_str(&format!("<section id=\"{}\" style=\"background:{}\">", escape(&section.id), escape(bg))),
+                None => html.push_str(&format!("<section id=\"{}\">", escape(&section.id))),
+            }
+            for block in &section.blocks { html.push_str(&self.render_block(block, visit)); }
+            html.push_str("</section>");
+        }
+        html.push_str("</body></html>");
+        html
+    }
+
+    fn render_block(&self, block: &PageBlock, visit: &PageVisit) -> String {
+        match block {
+            PageBlock::Heading { text, level } => { let l = (*level).clamp(1, 6); format!("<h{l}>{}</h{l}>", escape(text)) }
+            PageBlock::Text { html } | PageBlock::Html { html } => html.clone(),
+            PageBlock::Image { url, alt } => format!("<img src=\"{}\" alt=\"{}\">", escape(url), escape(alt)),
+            PageBlock::Button { label, url } => format!("<a class=\"button\" href=\"{}\">{}</a>", escape(url), escape(label)),
+            PageBlock::Video { url } => format!("<video controls src=\"{}\"></video>", escape(url)),
+            PageBlock::Form { form_id } => match self.form_for(&visit.tenant_id, form_id) {
+                Some(form) => render_form(&form, &visit.id),
+                None => String::new(),
+            },
+        }
+    }
+}
+
+fn render_form(form: &Form, visit_id: &str) -> String {
+    let mut html = format!("<form method=\"post\" action=\"/_forms/{}\"><input type=\"hidden\" name=\"_visit\" value=\"{}\">", escape(form.id()), escape(visit_id));
+    let mut fields: Vec<_> = form.fields().iter().collect();
+    fields.sort_by_key(|f| f.order);
+    for field in fields {
+        let required = if field.required { " required" } else { "" };
+        let name = escape(&field.id);
+        html.push_str(&format!("<label for=\"{name}\">{}</label>", escape(&field.label)));
+        let input = match &field.field_type {
+            FieldType::LongText => format!("<textarea id=\"{name}\" name=\"{name}\"{required}></textarea>"),
+            FieldType::Dropdown | FieldType::MultiSelect => {
+                let multiple = if matches!(field.field_type, FieldType::MultiSelect) { " multiple" } else { "" };
+                let options: String = field.options.iter().flatten().map(|o| format!("<option>{}</option>", escape(o))).collect();
+                format!("<select id=\"{name}\" name=\"{name}\"{multiple}{required}>{options}</select>")
+            }
+            other => {
+                let input_type = match other {
+                    FieldType::Email => "email", FieldType::Phone => "tel", FieldType::Number | FieldType::Rating => "number",
+                    FieldType::Date => "date", FieldType::Checkbox => "checkbox", FieldType::Radio => "radio", FieldType::FileUpload => "file",
+                    _ => "text",
+                };
+                let placeholder = field.placeholder.as_deref().map(|p| format!(" placeholder=\"{}\"", escape(p))).unwrap_or_default();
+                format!("<input type=\"{input_type}\" id=\"{name}\" name=\"{name}\"{placeholder}{required}>")
+            }
+        };
+        html.push_str(&input);
+    }
+    html.push_str("<button type=\"submit\">Submit</button></form>");
+    html
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)] pub enum HostingError { UnknownHost, DomainNotVerified, PageNotFound, PageNotPublished, FormNotFound, FormNotOnPage, VisitNotFound, AlreadyConverted }
+impl std::error::Error for HostingError {}
+impl std::fmt::Display for HostingError { fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "Hosting error: {:?}", self) } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_objects::{CampaignType, PageSection};
+    use sase_forms::FormField;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct RecordingSink(Arc<Mutex<Vec<Attribution>>>);
+
+    impl ContactSink for RecordingSink {
+        fn upsert_with_attribution(&self, _tenant_id: &str, email: &str, attribution: &Attribution) -> String {
+            self.0.lock().unwrap().push(attribution.clone());
+            format!("contact-{}", email)
+        }
+    }
+
+    fn host() -> (LandingPageHost, RecordingSink) {
+        let sink = RecordingSink::default();
+        (LandingPageHost::new("pages.example.com", Box::new(sink.clone())), sink)
+    }
+
+    fn form(host: &LandingPageHost, tenant_id: &str) -> String {
+        let mut form = Form::create("Get the whitepaper");
+        form.add_field(FormField { id: "email".into(), field_type: FieldType::Email, label: "Email".into(), placeholder: None, required: true, options: None, validation: None, order: 0 });
+        form.publish().unwrap();
+        let id = form.id().to_string();
+        host.add_form(tenant_id, form);
+        id
+    }
+
+    fn page(host: &LandingPageHost, tenant_id: &str, slug: &str, form_id: &str, campaign_id: Option<&str>) -> String {
+        let mut page = LandingPage::create(tenant_id, slug, slug);
+        page.add_section(PageSection { id: "hero".into(), blocks: vec![PageBlock::Form { form_id: form_id.into() }], background: None });
+        if let Some(campaign_id) = campaign_id { page.link_campaign(campaign_id); }
+        page.publish().unwrap();
+        let id = page.id().to_string();
+        host.add_page(page);
+        id
+    }
+
+    #[test]
+    fn test_cross_page_submission_is_rejected() {
+        let (host, sink) = host();
+        let form_a = form(&host, "acme");
+        let form_b = form(&host, "acme");
+        let page_a = page(&host, "acme", "offer-a", &form_a, None);
+        page(&host, "acme", "offer-b", &form_b, None);
+
+        let served = host.handle_request("acme.pages.example.com", "/offer-a", "visitor-1", None).unwrap();
+        assert!(served.html.contains(&format!("/_forms/{}", form_a)));
+        assert_eq!(host.submit_form(&served.visit_id, &form_b, "a@example.com", vec![]), Err(HostingError::FormNotOnPage));
+        assert_eq!(host.submit_form(&served.visit_id, "no-such-form", "a@example.com", vec![]), Err(HostingError::FormNotFound));
+
+        assert!(sink.0.lock().unwrap().is_empty());
+        assert_eq!(host.page(&page_a).unwrap().stats().conversions, 0);
+        assert!(!host.visit(&served.visit_id).unwrap().converted);
+    }
+
+    #[test]
+    fn test_other_tenants_form_is_not_served_or_accepted() {
+        let (host, _) = host();
+        let foreign = form(&host, "globex");
+        page(&host, "acme", "offer", &foreign, None);
+
+        let served = host.handle_request("acme.pages.example.com", "/offer", "visitor-1", None).unwrap();
+        assert!(!served.html.contains("<form"));
+        assert_eq!(host.submit_form(&served.visit_id, &foreign, "a@example.com", vec![]), Err(HostingError::FormNotFound));
+    }
+
+    #[test]
+    fn test_duplicate_submission_converts_once() {
+        let (host, sink) = host();
+        let campaign = Campaign::create("Spring", CampaignType::Email);
+        let campaign_id = campaign.id().to_string();
+        host.add_campaign(campaign);
+        let form_id = form(&host, "acme");
+        let page_id = page(&host, "acme", "offer", &form_id, Some(&campaign_id));
+
+        let served = host.handle_request("acme.pages.example.com", "/offer?utm_source=newsletter", "visitor-1", None).unwrap();
+        assert_eq!(host.submit_form(&served.visit_id, &form_id, "a@example.com", vec![]).unwrap(), "contact-a@example.com");
+        assert_eq!(host.submit_form(&served.visit_id, &form_id, "a@example.com", vec![]), Err(HostingError::AlreadyConverted));
+
+        assert_eq!(host.page(&page_id).unwrap().stats().conversions, 1);
+        assert_eq!(host.campaign(&campaign_id).unwrap().stats().conversions, 1);
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+        assert_eq!(sink.0.lock().unwrap()[0].utm.source.as_deref(), Some("newsletter"));
+    }
+
+    #[test]
+    fn test_first_touch_and_retention() {
+        let (host, sink) = host();
+        let host = host.with_retention(Duration::days(30));
+        let form_id = form(&host, "acme");
+        let page_id = page(&host, "acme", "offer", &form_id, None);
+
+        let first = host.handle_request("acme.pages.example.com", "/offer", "visitor-1", None).unwrap();
+        let second = host.handle_request("acme.pages.example.com", "/offer", "visitor-1", None).unwrap();
+        host.submit_form(&second.visit_id, &form_id, "a@example.com", vec![]).unwrap();
+        let first_visit = host.visit(&first.visit_id).unwrap();
+        assert_eq!(sink.0.lock().unwrap()[0].first_touch_at, first_visit.visited_at);
+        assert_eq!(host.page(&page_id).unwrap().stats().unique_visitors, 1);
+
+        host.purge_expired(Utc::now() + Duration::days(31));
+        assert!(host.visit(&first.visit_id).is_none());
+        assert!(host.touches.is_empty());
+
+        // A visitor gone past retention counts as new
+        host.handle_request("acme.pages.example.com", "/offer", "visitor-1", None).unwrap();
+        assert_eq!(host.page(&page_id).unwrap().stats().unique_visitors, 2);
+    }
+}
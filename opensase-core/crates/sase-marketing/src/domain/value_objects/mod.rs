@@ -27,3 +27,102 @@ pub struct FilterCondition {
     pub operator: String,
     pub value: String,
 }
+
+/// UTM parameters captured from a landing page visit
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct UtmParams {
+    pub source: Option<String>,
+    pub medium: Option<String>,
+    pub campaign: Option<String>,
+    pub term: Option<String>,
+    pub content: Option<String>,
+}
+
+impl UtmParams {
+    /// Parse `utm_*` parameters from a URL query string
+    pub fn from_query(query: &str) -> Self {
+        let mut utm = Self::default();
+        for pair in query.trim_start_matches('?').split('&') {
+            let Some((key, value)) = pair.split_once('=') else { continue };
+            if value.is_empty() { continue; }
+            let value = Some(decode_query_component(value));
+            match decode_query_component(key).as_str() {
+                "utm_source" => utm.source = value,
+                "utm_medium" => utm.medium = value,
+                "utm_campaign" => utm.campaign = value,
+                "utm_term" => utm.term = value,
+                "utm_content" => utm.content = value,
+                _ => {}
+            }
+        }
+        utm
+    }
+    pub fn is_empty(&self) -> bool { *self == Self::default() }
+}
+
+/// Decode `+` and `%XX` escapes (application/x-www-form-urlencoded).
+/// Malformed escapes are kept as-is and invalid UTF-8 is replaced.
+fn decode_query_component(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(byte) => { out.push(byte); i += 2; }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Landing page section (a row of blocks)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageSection {
+    pub id: String,
+    pub blocks: Vec<PageBlock>,
+    pub background: Option<String>,
+}
+
+/// Landing page content block
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PageBlock {
+    Heading { text: String, level: u8 },
+    Text { html: String },
+    Image { url: String, alt: String },
+    Button { label: String, url: String },
+    Video { url: String },
+    /// Embedded sase-forms form; submissions count as conversions
+    Form { form_id: String },
+    Html { html: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utm_params_percent_decoded() {
+        let utm = UtmParams::from_query(
+            "?utm_source=news%20letter&utm_medium=e-mail&utm_campaign=spring+sale%2B2024&utm_term=caf%C3%A9&utm_content=100%&ref=x",
+        );
+        assert_eq!(utm.source.as_deref(), Some("news letter"));
+        assert_eq!(utm.medium.as_deref(), Some("e-mail"));
+        assert_eq!(utm.campaign.as_deref(), Some("spring sale+2024"));
+        assert_eq!(utm.term.as_deref(), Some("café"));
+        assert_eq!(utm.content.as_deref(), Some("100%"));
+
+        assert_eq!(UtmParams::from_query("utm%5Fsource=ads").source.as_deref(), Some("ads"));
+        assert_eq!(UtmParams::from_query("utm_source=%zz%4").source.as_deref(), Some("%zz%4"));
+        assert!(UtmParams::from_query("utm_source=&other=1").is_empty());
+    }
+}
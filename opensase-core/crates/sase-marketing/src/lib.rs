@@ -1,4 +1,5 @@
 //! OpenSASE Marketing Platform - DDD Implementation (HubSpot replacement)
 pub mod domain;
-pub use domain::aggregates::{Campaign, Automation, CampaignError};
-pub use domain::events::{DomainEvent, CampaignEvent};
+pub use domain::aggregates::{Campaign, Automation, CampaignError, LandingPage, LandingPageError};
+pub use domain::events::{DomainEvent, CampaignEvent, LandingPageEvent};
+pub use domain::services::{LandingPageHost, HostingError};
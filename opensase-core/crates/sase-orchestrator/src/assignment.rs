@@ -0,0 +1,745 @@
+//! Load-Aware PoP Assignment
+//!
+//! Chooses the PoP a new client or edge attaches to. Candidates are ranked
+//! by measured RTT from the client's prefix, current load and health, after
+//! dropping PoPs that are in (or about to enter) a maintenance window or sit
+//! outside the tenant's data-residency boundary. Every assignment is handed
+//! out as a lease; PoPs approaching capacity shed a bounded share of their
+//! leases per rebalance pass instead of draining all at once.
+
+use crate::anycast::haversine_distance;
+use crate::health::{HealthMonitor, HealthStatus};
+use crate::pop::{Continent, PopDefinition, PopTier};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// RTT assumed when neither a measurement nor a client location is known
+const UNKNOWN_RTT_MS: f64 = 150.0;
+/// Fibre paths are rarely great-circle; inflate geographic estimates
+const ROUTE_INFLATION: f64 = 1.4;
+
+/// Assignment tuning
+#[derive(Debug, Clone)]
+pub struct AssignmentConfig {
+    /// Lease lifetime in seconds
+    pub lease_secs: u64,
+    /// Utilization (0.0 - 1.0) at which a PoP stops taking new assignments
+    pub max_utilization: f64,
+    /// Utilization at which existing leases start migrating away
+    pub rebalance_threshold: f64,
+    /// Fraction of an overloaded PoP's leases moved per rebalance pass
+    pub rebalance_step: f64,
+    /// Cost, in RTT milliseconds, of a fully loaded PoP
+    pub load_weight: f64,
+    /// Extra cost in milliseconds for a degraded PoP
+    pub degraded_penalty_ms: f64,
+    /// EWMA smoothing factor for RTT samples
+    pub rtt_alpha: f64,
+    /// Candidates returned with each lease
+    pub max_candidates: usize,
+}
+
+impl Default for AssignmentConfig {
+    fn default() -> Self {
+        Self {
+            lease_secs: 3600,
+            max_utilization: 0.90,
+            rebalance_threshold: 0.80,
+            rebalance_step: 0.05,
+            load_weight: 100.0,
+            degraded_penalty_ms: 50.0,
+            rtt_alpha: 0.3,
+            max_candidates: 3,
+        }
+    }
+}
+
+/// Load reported by a PoP
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PopLoad {
+    /// Active tunnel/proxy sessions
+    pub active_sessions: u64,
+    /// CPU utilization (0 - 100)
+    pub cpu_percent: f64,
+    /// Bandwidth currently in use
+    pub bandwidth_gbps: f64,
+}
+
+/// Scheduled maintenance window (unix seconds)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// Window start
+    pub start: u64,
+    /// Window end
+    pub end: u64,
+}
+
+impl MaintenanceWindow {
+    /// Whether the window intersects `[from, to)`
+    pub fn overlaps(&self, from: u64, to: u64) -> bool {
+        self.start < to && self.end > from
+    }
+}
+
+/// Data-residency boundary a tenant's traffic must stay within
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ResidencyConstraint {
+    /// No restriction
+    #[default]
+    Any,
+    /// PoP must be on one of these continents
+    Continents(Vec<Continent>),
+    /// PoP must be in one of these countries (ISO 3166 alpha-2)
+    Countries(Vec<String>),
+}
+
+impl ResidencyConstraint {
+    fn permits(&self, site: &PopSite) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Continents(continents) => continents.contains(&site.continent),
+            Self::Countries(countries) => countries.iter().any(|c| c.eq_ignore_ascii_case(&site.country)),
+        }
+    }
+}
+
+/// What is being attached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientKind {
+    /// Remote-access client
+    Client,
+    /// SD-WAN edge (needs a tunnel-terminating PoP)
+    Edge,
+}
+
+/// Assignment request
+#[derive(Debug, Clone)]
+pub struct AssignmentRequest {
+    /// Client or edge identifier
+    pub client_id: String,
+    /// Client kind
+    pub kind: ClientKind,
+    /// Public address the client connects from
+    pub client_ip: IpAddr,
+    /// Approximate location (lat, lon), used when no RTT is measured
+    pub location: Option<(f64, f64)>,
+    /// Residency boundary
+    pub residency: ResidencyConstraint,
+    /// Sessions this client is expected to open
+    pub sessions: u64,
+}
+
+impl AssignmentRequest {
+    /// Create request
+    pub fn new(client_id: &str, kind: ClientKind, client_ip: IpAddr) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            kind,
+            client_ip,
+            location: None,
+            residency: ResidencyConstraint::Any,
+            sessions: 1,
+        }
+    }
+
+    /// Set approximate location
+    pub fn with_location(mut self, lat: f64, lon: f64) -> Self {
+        self.location = Some((lat, lon));
+        self
+    }
+
+    /// Set residency boundary
+    pub fn with_residency(mut self, residency: ResidencyConstraint) -> Self {
+        self.residency = residency;
+        self
+    }
+
+    /// Set expected session count
+    pub fn with_sessions(mut self, sessions: u64) -> Self {
+        self.sessions = sessions.max(1);
+        self
+    }
+}
+
+/// Ranked PoP candidate
+#[derive(Debug, Clone)]
+pub struct PopCandidate {
+    /// PoP ID
+    pub pop_id: String,
+    /// Combined cost (lower is better)
+    pub cost: f64,
+    /// RTT used for scoring
+    pub rtt_ms: f64,
+    /// Whether `rtt_ms` was measured rather than estimated
+    pub rtt_measured: bool,
+    /// Utilization at scoring time (0.0 - 1.0)
+    pub utilization: f64,
+}
+
+/// Time-bound PoP assignment
+#[derive(Debug, Clone)]
+pub struct PopLease {
+    /// Lease ID
+    pub lease_id: String,
+    /// Client or edge identifier
+    pub client_id: String,
+    /// Assigned PoP
+    pub pop_id: String,
+    /// Ranked candidates, assigned PoP first; the rest are failover targets
+    pub candidates: Vec<PopCandidate>,
+    /// Issue time
+    pub issued_at: u64,
+    /// Expiry time
+    pub expires_at: u64,
+}
+
+/// Why a lease was moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReassignReason {
+    /// Source PoP above the rebalance threshold
+    Overloaded,
+    /// Source PoP entering maintenance before the lease expires
+    Maintenance,
+    /// Source PoP unhealthy
+    Unhealthy,
+}
+
+/// Lease moved by a rebalance pass
+#[derive(Debug, Clone)]
+pub struct Reassignment {
+    /// Lease ID
+    pub lease_id: String,
+    /// Client or edge identifier
+    pub client_id: String,
+    /// Previous PoP
+    pub from: String,
+    /// New PoP
+    pub to: String,
+    /// Reason
+    pub reason: ReassignReason,
+}
+
+/// Assignable PoP state
+#[derive(Debug, Clone)]
+struct PopSite {
+    pop_id: String,
+    tier: PopTier,
+    continent: Continent,
+    country: String,
+    latitude: f64,
+    longitude: f64,
+    max_sessions: u64,
+    bandwidth_gbps: f64,
+    load: PopLoad,
+    /// Sessions leased in or out since the last load report
+    session_delta: i64,
+    maintenance: Vec<MaintenanceWindow>,
+}
+
+impl PopSite {
+    fn sessions(&self) -> u64 {
+        (self.load.active_sessions as i64 + self.session_delta).max(0) as u64
+    }
+
+    fn utilization(&self) -> f64 {
+        let sessions = self.sessions() as f64 / self.max_sessions.max(1) as f64;
+        let cpu = self.load.cpu_percent / 100.0;
+        let bandwidth = if self.bandwidth_gbps > 0.0 {
+            self.load.bandwidth_gbps / self.bandwidth_gbps
+        } else {
+            0.0
+        };
+        sessions.max(cpu).max(bandwidth)
+    }
+
+    fn in_maintenance(&self, from: u64, to: u64) -> bool {
+        self.maintenance.iter().any(|w| w.overlaps(from, to))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LeaseRecord {
+    lease: PopLease,
+    request: AssignmentRequest,
+}
+
+/// Load-aware PoP assignment service
+pub struct PopAssignmentService {
+    sites: Arc<RwLock<HashMap<String, PopSite>>>,
+    /// Client prefix → PoP → smoothed RTT (ms)
+    rtt: Arc<RwLock<HashMap<String, HashMap<String, f64>>>>,
+    leases: Arc<RwLock<HashMap<String, LeaseRecord>>>,
+    health: Arc<HealthMonitor>,
+    config: AssignmentConfig,
+}
+
+impl PopAssignmentService {
+    /// Create service
+    pub fn new(health: Arc<HealthMonitor>) -> Self {
+        Self {
+            sites: Arc::new(RwLock::new(HashMap::new())),
+            rtt: Arc::new(RwLock::new(HashMap::new())),
+            leases: Arc::new(RwLock::new(HashMap::new())),
+            health,
+            config: AssignmentConfig::default(),
+        }
+    }
+
+    /// Set tuning
+    pub fn with_config(mut self, config: AssignmentConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Make a PoP assignable. `country` is the ISO code of its jurisdiction.
+    pub fn register_pop(&self, definition: &PopDefinition, country: &str) {
+        let capacity = &definition.capacity;
+        let site = PopSite {
+            pop_id: definition.pop_id.clone(),
+            tier: definition.tier,
+            continent: definition.region.continent,
+            country: country.to_uppercase(),
+            latitude: definition.region.latitude,
+            longitude: definition.region.longitude,
+            max_sessions: capacity.max_connections * capacity.instance_count.max(1) as u64,
+            bandwidth_gbps: capacity.bandwidth_gbps as f64 * capacity.instance_count.max(1) as f64,
+            load: PopLoad::default(),
+            session_delta: 0,
+            maintenance: Vec::new(),
+        };
+        self.sites.write().insert(site.pop_id.clone(), site);
+    }
+
+    /// Stop assigning to a PoP. Its leases move on the next rebalance.
+    pub fn deregister_pop(&self, pop_id: &str) {
+        self.sites.write().remove(pop_id);
+    }
+
+    /// Record a load report. Resets the lease-based adjustment since the
+    /// report already includes sessions from earlier assignments.
+    pub fn report_load(&self, pop_id: &str, load: PopLoad) -> Result<(), AssignmentError> {
+        let mut sites = self.sites.write();
+        let site = sites.get_mut(pop_id)
+            .ok_or_else(|| AssignmentError::UnknownPop(pop_id.to_string()))?;
+        site.load = load;
+        site.session_delta = 0;
+        Ok(())
+    }
+
+    /// Schedule a maintenance window
+    pub fn schedule_maintenance(&self, pop_id: &str, window: MaintenanceWindow) -> Result<(), AssignmentError> {
+        if window.end <= window.start {
+            return Err(AssignmentError::InvalidWindow);
+        }
+        let mut sites = self.sites.write();
+        let site = sites.get_mut(pop_id)
+            .ok_or_else(|| AssignmentError::UnknownPop(pop_id.to_string()))?;
+        site.maintenance.retain(|w| w.end > now());
+        site.maintenance.push(window);
+        Ok(())
+    }
+
+    /// Record an RTT sample from a client address to a PoP
+    pub fn record_rtt(&self, client_ip: IpAddr, pop_id: &str, rtt_ms: f64) {
+        let alpha = self.config.rtt_alpha;
+        let mut rtt = self.rtt.write();
+        rtt.entry(client_prefix(client_ip))
+            .or_default()
+            .entry(pop_id.to_string())
+            .and_modify(|v| *v = alpha * rtt_ms + (1.0 - alpha) * *v)
+            .or_insert(rtt_ms);
+    }
+
+    /// Rank eligible PoPs for a request, best first
+    pub fn rank(&self, request: &AssignmentRequest) -> Vec<PopCandidate> {
+        self.rank_at(request, now(), self.config.max_utilization, None)
+    }
+
+    /// Assign a PoP and issue a lease
+    pub fn assign(&self, request: &AssignmentRequest) -> Result<PopLease, AssignmentError> {
+        self.assign_at(request, now())
+    }
+
+    /// Extend a lease. If its PoP is no longer usable the client is moved
+    /// to the best current candidate.
+    pub fn renew(&self, lease_id: &str) -> Result<PopLease, AssignmentError> {
+        self.renew_at(lease_id, now())
+    }
+
+    /// Release a lease
+    pub fn release(&self, lease_id: &str) -> Result<(), AssignmentError> {
+        let record = self.leases.write().remove(lease_id)
+            .ok_or_else(|| AssignmentError::LeaseNotFound(lease_id.to_string()))?;
+        self.adjust_sessions(&record.lease.pop_id, -(record.request.sessions as i64));
+        Ok(())
+    }
+
+    /// Get lease
+    pub fn get_lease(&self, lease_id: &str) -> Option<PopLease> {
+        self.leases.read().get(lease_id).map(|r| r.lease.clone())
+    }
+
+    /// Active leases on a PoP
+    pub fn leases_on(&self, pop_id: &str) -> Vec<PopLease> {
+        self.leases.read()
+            .values()
+            .filter(|r| r.lease.pop_id == pop_id)
+            .map(|r| r.lease.clone())
+            .collect()
+    }
+
+    /// Current utilization of a PoP (0.0 - 1.0)
+    pub fn utilization(&self, pop_id: &str) -> Option<f64> {
+        self.sites.read().get(pop_id).map(PopSite::utilization)
+    }
+
+    /// Move leases off PoPs that are overloaded, unhealthy or heading into
+    /// maintenance. Overloaded PoPs shed at most `rebalance_step` of their
+    /// leases per pass; the others are drained fully.
+    pub fn rebalance(&self) -> Vec<Reassignment> {
+        self.rebalance_at(now())
+    }
+
+    fn assign_at(&self, request: &AssignmentRequest, now: u64) -> Result<PopLease, AssignmentError> {
+        let candidates = self.rank_at(request, now, self.config.max_utilization, None);
+        let best = candidates.first().ok_or(AssignmentError::NoEligiblePop)?;
+
+        let lease = PopLease {
+            lease_id: uuid::Uuid::new_v4().to_string(),
+            client_id: request.client_id.clone(),
+            pop_id: best.pop_id.clone(),
+            candidates: candidates.clone(),
+            issued_at: now,
+            expires_at: now + self.config.lease_secs,
+        };
+        self.adjust_sessions(&lease.pop_id, request.sessions as i64);
+        self.leases.write().insert(lease.lease_id.clone(), LeaseRecord {
+            lease: lease.clone(),
+            request: request.clone(),
+        });
+
+        tracing::debug!(client = %request.client_id, pop = %lease.pop_id, "PoP assigned");
+        Ok(lease)
+    }
+
+    fn renew_at(&self, lease_id: &str, now: u64) -> Result<PopLease, AssignmentError> {
+        let record = self.leases.read().get(lease_id).cloned()
+            .ok_or_else(|| AssignmentError::LeaseNotFound(lease_id.to_string()))?;
+        let expires_at = now + self.config.lease_secs;
+
+        if self.usable_for_lease(&record.lease.pop_id, now, expires_at) {
+            let mut leases = self.leases.write();
+            let stored = leases.get_mut(lease_id)
+                .ok_or_else(|| AssignmentError::LeaseNotFound(lease_id.to_string()))?;
+            stored.lease.expires_at = expires_at;
+            return Ok(stored.lease.clone());
+        }
+
+        let candidates = self.rank_at(&record.request, now, self.config.max_utilization, Some(&record.lease.pop_id));
+        let best = candidates.first().ok_or(AssignmentError::NoEligiblePop)?;
+        Ok(self.move_lease(lease_id, &record, best.pop_id.clone(), candidates, now))
+    }
+
+    fn rebalance_at(&self, now: u64) -> Vec<Reassignment> {
+        self.leases.write().retain(|_, r| r.lease.expires_at > now);
+
+        let mut by_pop: HashMap<String, Vec<LeaseRecord>> = HashMap::new();
+        for record in self.leases.read().values() {
+            by_pop.entry(record.lease.pop_id.clone()).or_default().push(record.clone());
+        }
+
+        let mut moves = Vec::new();
+        for (pop_id, records) in by_pop {
+            let site = self.sites.read().get(&pop_id).cloned();
+            let unhealthy = site.is_none()
+                || self.health.get_status(&pop_id) == Some(HealthStatus::Unhealthy);
+            let overloaded = site.as_ref()
+                .is_some_and(|s| s.utilization() >= self.config.rebalance_threshold);
+
+            // Unhealthy PoPs and leases that would outlive a maintenance start
+            // are drained fully; overload only sheds a slice per pass
+            let mut drain = Vec::new();
+            let mut shed = Vec::new();
+            for record in records {
+                if unhealthy {
+                    drain.push((record, ReassignReason::Unhealthy));
+                } else if site.as_ref().is_some_and(|s| s.in_maintenance(now, record.lease.expires_at)) {
+                    drain.push((record, ReassignReason::Maintenance));
+                } else if overloaded {
+                    shed.push((record, ReassignReason::Overloaded));
+                }
+            }
+            let budget = drain.len()
+                + ((shed.len() as f64 * self.config.rebalance_step).ceil() as usize).min(shed.len());
+            let affected = drain.into_iter().chain(shed);
+
+            // Prefer moving clients that lose the least by moving
+            let mut planned: Vec<_> = affected
+                .filter_map(|(record, reason)| {
+                    let candidates = self.rank_at(&record.request, now, self.config.rebalance_threshold, Some(&pop_id));
+                    let best = candidates.first()?;
+                    let current = record.lease.candidates.iter()
+                        .find(|c| c.pop_id == pop_id)
+                        .map(|c| c.cost)
+                        .unwrap_or(0.0);
+                    let urgent = reason != ReassignReason::Overloaded;
+                    Some((urgent, best.cost - current, record, reason))
+                })
+                .collect();
+            planned.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.total_cmp(&b.1)));
+
+            let mut moved = 0;
+            for (_, _, record, reason) in planned {
+                if moved >= budget {
+                    break;
+                }
+                // Re-rank so earlier moves in this pass count against targets
+                let candidates = self.rank_at(&record.request, now, self.config.rebalance_threshold, Some(&pop_id));
+                let Some(best) = candidates.first() else { continue };
+                let to = best.pop_id.clone();
+                self.move_lease(&record.lease.lease_id, &record, to.clone(), candidates, now);
+                moves.push(Reassignment {
+                    lease_id: record.lease.lease_id.clone(),
+                    client_id: record.lease.client_id.clone(),
+                    from: pop_id.clone(),
+                    to,
+                    reason,
+                });
+                moved += 1;
+            }
+        }
+
+        if !moves.is_empty() {
+            tracing::info!(moved = moves.len(), "PoP leases rebalanced");
+        }
+        moves
+    }
+
+    fn rank_at(
+        &self,
+        request: &AssignmentRequest,
+        now: u64,
+        utilization_cap: f64,
+        exclude: Option<&str>,
+    ) -> Vec<PopCandidate> {
+        let lease_end = now + self.config.lease_secs;
+        let sites = self.sites.read();
+        let prefix_rtt = self.rtt.read().get(&client_prefix(request.client_ip)).cloned();
+
+        let mut candidates: Vec<_> = sites.values()
+            .filter(|s| Some(s.pop_id.as_str()) != exclude)
+            .filter(|s| request.kind != ClientKind::Edge || s.tier != PopTier::Cache)
+            .filter(|s| request.residency.permits(s))
+            .filter(|s| !s.in_maintenance(now, lease_end))
+            .filter_map(|site| {
+                let health = self.health.get_status(&site.pop_id);
+                if health == Some(HealthStatus::Unhealthy) {
+                    return None;
+                }
+
+                let extra = request.sessions as f64 / site.max_sessions.max(1) as f64;
+                let utilization = site.utilization();
+                if utilization + extra > utilization_cap {
+                    return None;
+                }
+
+                let measured = prefix_rtt.as_ref().and_then(|m| m.get(&site.pop_id)).copied();
+                let rtt_ms = measured.unwrap_or_else(|| match request.location {
+                    Some((lat, lon)) => {
+                        let km = haversine_distance(lat, lon, site.latitude, site.longitude);
+                        km / 100.0 * ROUTE_INFLATION
+                    }
+                    None => UNKNOWN_RTT_MS,
+                });
+
+                let mut cost = rtt_ms + self.config.load_weight * utilization.powi(2);
+                if health == Some(HealthStatus::Degraded) {
+                    cost += self.config.degraded_penalty_ms;
+                }
+
+                Some(PopCandidate {
+                    pop_id: site.pop_id.clone(),
+                    cost,
+                    rtt_ms,
+                    rtt_measured: measured.is_some(),
+                    utilization,
+                })
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+        candidates.truncate(self.config.max_candidates.max(1));
+        candidates
+    }
+
+    fn usable_for_lease(&self, pop_id: &str, now: u64, expires_at: u64) -> bool {
+        let sites = self.sites.read();
+        let Some(site) = sites.get(pop_id) else { return false };
+        self.health.get_status(pop_id) != Some(HealthStatus::Unhealthy)
+            && !site.in_maintenance(now, expires_at)
+    }
+
+    fn move_lease(
+        &self,
+        lease_id: &str,
+        record: &LeaseRecord,
+        to: String,
+        candidates: Vec<PopCandidate>,
+        now: u64,
+    ) -> PopLease {
+        let sessions = record.request.sessions as i64;
+        self.adjust_sessions(&record.lease.pop_id, -sessions);
+        self.adjust_sessions(&to, sessions);
+
+        let mut lease = record.lease.clone();
+        lease.pop_id = to;
+        lease.candidates = candidates;
+        lease.expires_at = now + self.config.lease_secs;
+
+        if let Some(stored) = self.leases.write().get_mut(lease_id) {
+            stored.lease = lease.clone();
+        }
+        lease
+    }
+
+    fn adjust_sessions(&self, pop_id: &str, delta: i64) {
+        if let Some(site) = self.sites.write().get_mut(pop_id) {
+            site.session_delta += delta;
+        }
+    }
+}
+
+/// Aggregation key for RTT samples: /24 for IPv4, /48 for IPv6
+pub fn client_prefix(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let o = v4.octets();
+            format!("{}.{}.{}.0/24", o[0], o[1], o[2])
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2])
+        }
+    }
+}
+
+/// Assignment errors
+#[derive(Debug, thiserror::Error)]
+pub enum AssignmentError {
+    /// PoP not registered
+    #[error("unknown PoP: {0}")]
+    UnknownPop(String),
+    /// Lease expired or released
+    #[error("lease not found: {0}")]
+    LeaseNotFound(String),
+    /// Every candidate filtered out
+    #[error("no eligible PoP")]
+    NoEligiblePop,
+    /// Window end not after start
+    #[error("maintenance window ends before it starts")]
+    InvalidWindow,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pop::{CapacitySpec, Region};
+    use crate::provider::DedicatedProvider;
+
+    fn service() -> PopAssignmentService {
+        let svc = PopAssignmentService::new(Arc::new(HealthMonitor::new()));
+        let capacity = CapacitySpec { max_connections: 100, instance_count: 1, ..CapacitySpec::default() };
+        let fra = PopDefinition::new(
+            "pop-fra",
+            Region::new("fra1", "Frankfurt", Continent::Europe, 50.11, 8.68),
+            DedicatedProvider::Hetzner,
+            PopTier::Core,
+        ).with_capacity(capacity.clone());
+        let ams = PopDefinition::new(
+            "pop-ams",
+            Region::new("ams1", "Amsterdam", Continent::Europe, 52.37, 4.90),
+            DedicatedProvider::OvhCloud,
+            PopTier::Core,
+        ).with_capacity(capacity.clone());
+        let nyc = PopDefinition::new(
+            "pop-nyc",
+            Region::new("nyc1", "New York", Continent::NorthAmerica, 40.71, -74.00),
+            DedicatedProvider::Voxility,
+            PopTier::Core,
+        ).with_capacity(capacity);
+        svc.register_pop(&fra, "DE");
+        svc.register_pop(&ams, "NL");
+        svc.register_pop(&nyc, "US");
+        svc
+    }
+
+    fn client(id: &str) -> AssignmentRequest {
+        AssignmentRequest::new(id, ClientKind::Client, "203.0.113.10".parse().unwrap())
+    }
+
+    #[test]
+    fn test_rank_uses_rtt_load_and_residency() {
+        let svc = service();
+        let ip: IpAddr = "203.0.113.10".parse().unwrap();
+        svc.record_rtt(ip, "pop-fra", 12.0);
+        svc.record_rtt(ip, "pop-ams", 18.0);
+        svc.record_rtt(ip, "pop-nyc", 5.0);
+
+        let eu = client("c1").with_residency(ResidencyConstraint::Continents(vec![Continent::Europe]));
+        let ranked = svc.rank(&eu);
+        assert_eq!(ranked[0].pop_id, "pop-fra");
+        assert!(ranked.iter().all(|c| c.pop_id != "pop-nyc"));
+
+        // Heavy load on Frankfurt outweighs its RTT advantage
+        svc.report_load("pop-fra", PopLoad { active_sessions: 85, ..PopLoad::default() }).unwrap();
+        assert_eq!(svc.rank(&eu)[0].pop_id, "pop-ams");
+    }
+
+    #[test]
+    fn test_maintenance_excludes_pop_and_renew_moves_lease() {
+        let svc = service();
+        let request = client("c1").with_location(50.0, 8.0)
+            .with_residency(ResidencyConstraint::Countries(vec!["de".into(), "nl".into()]));
+        let lease = svc.assign(&request).unwrap();
+        assert_eq!(lease.pop_id, "pop-fra");
+
+        let start = now() + 600;
+        svc.schedule_maintenance("pop-fra", MaintenanceWindow { start, end: start + 3600 }).unwrap();
+        assert!(svc.rank(&request).iter().all(|c| c.pop_id != "pop-fra"));
+
+        let renewed = svc.renew(&lease.lease_id).unwrap();
+        assert_eq!(renewed.pop_id, "pop-ams");
+        assert_eq!(svc.leases_on("pop-fra").len(), 0);
+    }
+
+    #[test]
+    fn test_rebalance_is_gradual() {
+        let svc = service();
+        let europe = ResidencyConstraint::Continents(vec![Continent::Europe]);
+        let mut leases = Vec::new();
+        for i in 0..40 {
+            let request = client(&format!("c{i}")).with_location(50.1, 8.7).with_residency(europe.clone());
+            leases.push(svc.assign(&request).unwrap());
+        }
+        assert!(leases.iter().any(|l| l.pop_id == "pop-fra"));
+
+        let on_fra = svc.leases_on("pop-fra").len();
+        svc.report_load("pop-fra", PopLoad { active_sessions: 85, ..PopLoad::default() }).unwrap();
+        svc.report_load("pop-ams", PopLoad::default()).unwrap();
+
+        let moves = svc.rebalance();
+        let expected = (on_fra as f64 * 0.05).ceil() as usize;
+        assert_eq!(moves.len(), expected);
+        assert!(moves.iter().all(|m| m.from == "pop-fra" && m.to == "pop-ams"));
+        assert!(moves.iter().all(|m| m.reason == ReassignReason::Overloaded));
+    }
+}
@@ -52,6 +52,7 @@ pub mod config;
 pub mod cost;
 pub mod capacity;
 pub mod saga;
+pub mod assignment;
 
 pub use pop::{PopDefinition, PopTier, Region, CapacitySpec};
 pub use provider::{DedicatedProvider, CloudProvider};  // CloudProvider is alias for backwards compat
@@ -61,4 +62,5 @@ pub use backbone::BackboneMesh;
 pub use config::ConfigManager;
 pub use cost::CostOptimizer;
 pub use capacity::CapacityPlanner;
+pub use assignment::PopAssignmentService;
 
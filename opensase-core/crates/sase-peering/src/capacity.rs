@@ -0,0 +1,540 @@
+//! IXP Port Capacity Planning
+//!
+//! Tracks per-port utilization (95th percentile, growth trend), forecasts
+//! when a port will cross its saturation threshold and proposes fixes:
+//! a faster port, an extra LAG member, or shifting sessions to another
+//! IXP where the same peers are present. Every proposal is costed through
+//! an [`UpgradeCostModel`] before it is returned.
+
+use crate::{IxpManager, IxpPort, PeeringSession, PortMetrics, estimate_port_cost};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Standard IXP port speeds in Mbps
+const PORT_SPEEDS: [u32; 5] = [1_000, 10_000, 100_000, 400_000, 800_000];
+
+/// One utilization reading for a port
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct UtilizationSample {
+    pub timestamp: DateTime<Utc>,
+    pub in_mbps: f64,
+    pub out_mbps: f64,
+}
+
+impl UtilizationSample {
+    /// Billable rate: the busier direction
+    pub fn peak_mbps(&self) -> f64 {
+        self.in_mbps.max(self.out_mbps)
+    }
+}
+
+/// Capacity planning configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityConfig {
+    /// Port is considered saturated above this fraction of its speed
+    pub saturation_threshold: f64,
+    /// How far ahead to forecast
+    pub horizon_days: i64,
+    /// Sample history kept per port
+    pub window_days: i64,
+    /// Days of history needed before a trend is reported
+    pub min_trend_days: usize,
+}
+
+impl Default for CapacityConfig {
+    fn default() -> Self {
+        Self {
+            saturation_threshold: 0.8,
+            horizon_days: 180,
+            window_days: 90,
+            min_trend_days: 7,
+        }
+    }
+}
+
+/// Utilization summary and forecast for a port
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortUtilization {
+    pub port_id: String,
+    pub speed_mbps: u32,
+    /// 95th percentile over the most recent 30 days
+    pub p95_mbps: f64,
+    pub peak_mbps: f64,
+    pub p95_percent: f64,
+    /// Linear growth of the daily 95th percentile
+    pub growth_mbps_per_day: Option<f64>,
+    /// Projected 95th percentile at the end of the horizon
+    pub projected_p95_mbps: Option<f64>,
+    /// When the p95 crosses the saturation threshold, if within the horizon
+    pub saturation_date: Option<DateTime<Utc>>,
+    pub samples: usize,
+}
+
+/// Proposed change to relieve a port
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CapacityAction {
+    UpgradePortSpeed { from_mbps: u32, to_mbps: u32 },
+    AddLagMember { member_speed_mbps: u32, total_mbps: u32 },
+    ShiftSessions {
+        to_port_id: String,
+        to_pop: String,
+        session_ids: Vec<String>,
+        shift_mbps: f64,
+    },
+}
+
+/// Cost of a proposal
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpgradeCost {
+    /// Change in recurring monthly cost
+    pub monthly_delta: f64,
+    pub one_time: f64,
+    /// `false` when the change does not fit the configured budget
+    pub within_budget: bool,
+    pub notes: Vec<String>,
+}
+
+/// Prices capacity changes before they are proposed.
+///
+/// Implemented by the backbone cost optimizer so that session shifts
+/// include inter-PoP transport and budget checks.
+pub trait UpgradeCostModel {
+    fn cost(&self, port: &IxpPort, action: &CapacityAction) -> UpgradeCost;
+}
+
+/// Port-fee-only cost model based on [`estimate_port_cost`]
+#[derive(Debug, Clone, Default)]
+pub struct PortFeeCostModel {
+    /// One-time cross-connect / install fee per new or changed port
+    pub install_fee: f64,
+}
+
+impl UpgradeCostModel for PortFeeCostModel {
+    fn cost(&self, port: &IxpPort, action: &CapacityAction) -> UpgradeCost {
+        let monthly_delta = match action {
+            CapacityAction::UpgradePortSpeed { to_mbps, .. } => {
+                estimate_port_cost(&port.ixp_name, *to_mbps) - port.monthly_cost
+            }
+            CapacityAction::AddLagMember { member_speed_mbps, .. } => {
+                estimate_port_cost(&port.ixp_name, *member_speed_mbps)
+            }
+            CapacityAction::ShiftSessions { .. } => 0.0,
+        };
+        let one_time = match action {
+            CapacityAction::ShiftSessions { .. } => 0.0,
+            _ => self.install_fee,
+        };
+
+        UpgradeCost {
+            monthly_delta,
+            one_time,
+            within_budget: true,
+            notes: Vec::new(),
+        }
+    }
+}
+
+/// Port fees plus inter-PoP transport for session shifts, checked
+/// against the backbone budget
+impl UpgradeCostModel for sase_backbone::cost_optimizer::CostOptimizer {
+    fn cost(&self, port: &IxpPort, action: &CapacityAction) -> UpgradeCost {
+        let mut cost = PortFeeCostModel::default().cost(port, action);
+
+        if let CapacityAction::ShiftSessions { to_pop, shift_mbps, .. } = action {
+            if *to_pop != port.pop_name {
+                let (provider, transport) = self.incremental_transport_cost(&port.pop_name, to_pop, shift_mbps.ceil() as u32);
+                let transport = transport.to_f64().unwrap_or(0.0);
+                cost.monthly_delta += transport;
+                cost.notes.push(format!(
+                    "backbone {} -> {} via {:?}: {:.2}/month", port.pop_name, to_pop, provider, transport,
+                ));
+            }
+        }
+
+        cost.within_budget = self.budget()
+            .and_then(|b| b.to_f64())
+            .map(|b| cost.monthly_delta <= b)
+            .unwrap_or(true);
+        cost
+    }
+}
+
+/// How soon a recommendation needs action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Planned,
+    Soon,
+    Immediate,
+}
+
+/// Costed recommendation for a port nearing saturation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityRecommendation {
+    pub port_id: String,
+    pub action: CapacityAction,
+    pub reason: String,
+    pub urgency: Urgency,
+    pub saturation_date: Option<DateTime<Utc>>,
+    pub cost: UpgradeCost,
+    /// Cheapest in-budget option for this port
+    pub preferred: bool,
+}
+
+/// Per-port utilization tracker and forecaster
+pub struct CapacityPlanner {
+    config: CapacityConfig,
+    samples: HashMap<String, VecDeque<UtilizationSample>>,
+    /// Recent traffic per peering session (Mbps), e.g. from sFlow
+    session_traffic: HashMap<String, f64>,
+}
+
+impl CapacityPlanner {
+    pub fn new(config: CapacityConfig) -> Self {
+        Self {
+            config,
+            samples: HashMap::new(),
+            session_traffic: HashMap::new(),
+        }
+    }
+
+    /// Record a utilization sample
+    pub fn record(&mut self, port_id: &str, sample: UtilizationSample) {
+        let cutoff = sample.timestamp - Duration::days(self.config.window_days);
+        let history = self.samples.entry(port_id.to_string()).or_default();
+        history.push_back(sample);
+        while history.front().map(|s| s.timestamp < cutoff).unwrap_or(false) {
+            history.pop_front();
+        }
+    }
+
+    /// Record from exported port metrics
+    pub fn record_metrics(&mut self, metrics: &PortMetrics, timestamp: DateTime<Utc>) {
+        let speed = metrics.speed_mbps as f64;
+        self.record(&metrics.port_id, UtilizationSample {
+            timestamp,
+            in_mbps: speed * metrics.utilization_in_percent as f64 / 100.0,
+            out_mbps: speed * metrics.utilization_out_percent as f64 / 100.0,
+        });
+    }
+
+    /// Record current traffic for a peering session
+    pub fn record_session_traffic(&mut self, session_id: &str, mbps: f64) {
+        self.session_traffic.insert(session_id.to_string(), mbps);
+    }
+
+    /// Utilization and saturation forecast for a port
+    pub fn utilization(&self, port: &IxpPort) -> Option<PortUtilization> {
+        let history = self.samples.get(&port.id).filter(|h| !h.is_empty())?;
+        let latest = history.back()?.timestamp;
+        let speed = port.speed_mbps as f64;
+
+        let recent: Vec<f64> = history.iter()
+            .filter(|s| s.timestamp >= latest - Duration::days(30))
+            .map(|s| s.peak_mbps())
+            .collect();
+        let p95 = percentile(&recent, 0.95);
+        let peak = recent.iter().cloned().fold(0.0, f64::max);
+
+        // Trend over daily 95th percentiles to damp intra-day bursts
+        let mut daily: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+        for s in history {
+            daily.entry(s.timestamp.date_naive()).or_default().push(s.peak_mbps());
+        }
+        let points: Vec<(f64, f64)> = daily.iter()
+            .map(|(day, values)| {
+                let days = (*day - latest.date_naive()).num_days() as f64;
+                (days, percentile(values, 0.95))
+            })
+            .collect();
+        let trend = (points.len() >= self.config.min_trend_days)
+            .then(|| linear_fit(&points))
+            .flatten();
+
+        let threshold = speed * self.config.saturation_threshold;
+        let horizon = self.config.horizon_days as f64;
+        let (growth, projected, saturation_date) = match trend {
+            Some((slope, intercept)) => {
+                let projected = intercept + slope * horizon;
+                let date = if intercept.max(p95) >= threshold {
+                    Some(latest)
+                } else if slope > 0.0 {
+                    let days = (threshold - intercept) / slope;
+                    (days <= horizon).then(|| latest + Duration::seconds((days * 86_400.0) as i64))
+                } else {
+                    None
+                };
+                (Some(slope), Some(projected), date)
+            }
+            None => (None, None, (p95 >= threshold).then_some(latest)),
+        };
+
+        Some(PortUtilization {
+            port_id: port.id.clone(),
+            speed_mbps: port.speed_mbps,
+            p95_mbps: p95,
+            peak_mbps: peak,
+            p95_percent: if speed > 0.0 { p95 / speed * 100.0 } else { 0.0 },
+            growth_mbps_per_day: growth,
+            projected_p95_mbps: projected,
+            saturation_date,
+            samples: history.len(),
+        })
+    }
+
+    /// Costed recommendations for every active port forecast to saturate
+    /// within the horizon
+    pub fn recommend(
+        &self,
+        ixps: &IxpManager,
+        sessions: &[PeeringSession],
+        cost_model: &dyn UpgradeCostModel,
+    ) -> Vec<CapacityRecommendation> {
+        let mut recommendations = Vec::new();
+
+        for port in ixps.active_ports() {
+            let Some(util) = self.utilization(port) else { continue };
+            let Some(saturation_date) = util.saturation_date else { continue };
+
+            let days_left = (saturation_date - Utc::now()).num_days();
+            let urgency = match days_left {
+                d if d <= 14 => Urgency::Immediate,
+                d if d <= 60 => Urgency::Soon,
+                _ => Urgency::Planned,
+            };
+
+            // Capacity needed so the horizon projection stays under threshold
+            let demand = util.projected_p95_mbps.unwrap_or(util.p95_mbps).max(util.p95_mbps);
+            let required = demand / self.config.saturation_threshold;
+            let reason = format!(
+                "p95 {:.0} Mbps ({:.0}% of {} Mbps), saturating {}",
+                util.p95_mbps, util.p95_percent, port.speed_mbps, saturation_date.format("%Y-%m-%d"),
+            );
+
+            let mut options = Vec::new();
+            if let Some(&to_mbps) = PORT_SPEEDS.iter().find(|&&s| s as f64 >= required && s > port.speed_mbps) {
+                options.push(CapacityAction::UpgradePortSpeed { from_mbps: port.speed_mbps, to_mbps });
+            }
+            if (port.speed_mbps as f64) * 2.0 >= required {
+                options.push(CapacityAction::AddLagMember {
+                    member_speed_mbps: port.speed_mbps,
+                    total_mbps: port.speed_mbps * 2,
+                });
+            }
+            if let Some(shift) = self.shift_candidate(port, demand, ixps, sessions) {
+                options.push(shift);
+            }
+
+            let mut costed: Vec<CapacityRecommendation> = options.into_iter()
+                .map(|action| CapacityRecommendation {
+                    port_id: port.id.clone(),
+                    cost: cost_model.cost(port, &action),
+                    action,
+                    reason: reason.clone(),
+                    urgency,
+                    saturation_date: Some(saturation_date),
+                    preferred: false,
+                })
+                .collect();
+            costed.sort_by(|a, b| {
+                b.cost.within_budget.cmp(&a.cost.within_budget)
+                    .then(a.cost.monthly_delta.total_cmp(&b.cost.monthly_delta))
+            });
+            if let Some(best) = costed.first_mut().filter(|r| r.cost.within_budget) {
+                best.preferred = true;
+            }
+
+            recommendations.extend(costed);
+        }
+
+        recommendations.sort_by_key(|r| std::cmp::Reverse(r.urgency));
+        recommendations
+    }
+
+    /// Move the heaviest sessions whose peers we also meet on another port
+    /// with headroom, until the excess over threshold is covered
+    fn shift_candidate(
+        &self,
+        port: &IxpPort,
+        demand: f64,
+        ixps: &IxpManager,
+        sessions: &[PeeringSession],
+    ) -> Option<CapacityAction> {
+        let excess = demand - port.speed_mbps as f64 * self.config.saturation_threshold;
+        if excess <= 0.0 {
+            return None;
+        }
+
+        let mut best: Option<CapacityAction> = None;
+        for target in ixps.active_ports() {
+            if target.id == port.id || target.ixp_id == port.ixp_id {
+                continue;
+            }
+            let target_load = self.utilization(target)
+                .map(|u| u.projected_p95_mbps.unwrap_or(u.p95_mbps).max(u.p95_mbps))
+                .unwrap_or(0.0);
+            let headroom = target.speed_mbps as f64 * self.config.saturation_threshold - target_load;
+            if headroom < excess {
+                continue;
+            }
+
+            let peers_at_target: HashSet<u32> = sessions.iter()
+                .filter(|s| s.ixp_port_id == target.id && s.is_healthy())
+                .map(|s| s.peer_asn)
+                .collect();
+            let mut movable: Vec<(&PeeringSession, f64)> = sessions.iter()
+                .filter(|s| s.ixp_port_id == port.id && peers_at_target.contains(&s.peer_asn))
+                .filter_map(|s| self.session_traffic.get(&s.id).map(|t| (s, *t)))
+                .collect();
+            movable.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let mut shifted = 0.0;
+            let mut session_ids = Vec::new();
+            for (session, traffic) in movable {
+                if shifted >= excess || shifted + traffic > headroom {
+                    continue;
+                }
+                shifted += traffic;
+                session_ids.push(session.id.clone());
+            }
+            if shifted < excess {
+                continue;
+            }
+
+            let better = match &best {
+                Some(CapacityAction::ShiftSessions { session_ids: current, .. }) => session_ids.len() < current.len(),
+                _ => true,
+            };
+            if better {
+                best = Some(CapacityAction::ShiftSessions {
+                    to_port_id: target.id.clone(),
+                    to_pop: target.pop_name.clone(),
+                    session_ids,
+                    shift_mbps: shifted,
+                });
+            }
+        }
+        best
+    }
+}
+
+impl Default for CapacityPlanner {
+    fn default() -> Self {
+        Self::new(CapacityConfig::default())
+    }
+}
+
+/// Nearest-rank percentile
+fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+/// Least-squares fit returning (slope, intercept at x = 0)
+fn linear_fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let var_x: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    if var_x == 0.0 {
+        return None;
+    }
+    let cov: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let slope = cov / var_x;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BgpSessionState, IxpConnectionStatus, PeeringType};
+
+    fn port(id: &str, ixp_id: u32, speed_mbps: u32) -> IxpPort {
+        IxpPort {
+            id: id.to_string(),
+            ixp_id,
+            ixp_name: "Local IX".to_string(),
+            pop_name: format!("pop-{}", ixp_id),
+            speed_mbps,
+            ipv4_address: None,
+            ipv6_address: None,
+            vlan_id: 100,
+            status: IxpConnectionStatus::Active,
+            monthly_cost: estimate_port_cost("Local IX", speed_mbps),
+        }
+    }
+
+    fn session(id: &str, port_id: &str, peer_asn: u32) -> PeeringSession {
+        PeeringSession {
+            id: id.to_string(),
+            ixp_port_id: port_id.to_string(),
+            peer_asn,
+            peer_name: format!("AS{}", peer_asn),
+            peer_ip: "192.0.2.1".parse().unwrap(),
+            local_ip: "192.0.2.2".parse().unwrap(),
+            peering_type: PeeringType::Bilateral,
+            state: BgpSessionState::Established,
+            prefixes_received: 100,
+            prefixes_sent: 10,
+            uptime_seconds: 86400,
+            last_state_change: 0,
+        }
+    }
+
+    #[test]
+    fn test_forecast_and_recommendations() {
+        let mut ixps = IxpManager::new();
+        ixps.add_port(port("busy", 1, 10_000));
+        ixps.add_port(port("spare", 2, 10_000));
+
+        // ~6.5 Gbps today growing by 50 Mbps/day: crosses 8 Gbps in ~31 days
+        let mut planner = CapacityPlanner::new(CapacityConfig { horizon_days: 90, ..Default::default() });
+        let start = Utc::now() - Duration::days(29);
+        for day in 0..30 {
+            for hour in 0..24 {
+                let mbps = 5_000.0 + 50.0 * day as f64 + if hour == 20 { 200.0 } else { 0.0 };
+                planner.record("busy", UtilizationSample {
+                    timestamp: start + Duration::days(day) + Duration::hours(hour),
+                    in_mbps: mbps,
+                    out_mbps: mbps * 0.5,
+                });
+            }
+            planner.record("spare", UtilizationSample {
+                timestamp: start + Duration::days(day),
+                in_mbps: 1_000.0,
+                out_mbps: 500.0,
+            });
+        }
+
+        let util = planner.utilization(ixps.get_port("busy").unwrap()).unwrap();
+        assert!((util.growth_mbps_per_day.unwrap() - 50.0).abs() < 1.0);
+        let days = (util.saturation_date.unwrap() - Utc::now()).num_days();
+        assert!((25..=40).contains(&days), "saturates in {} days", days);
+
+        let sessions = vec![session("s1", "busy", 13335), session("s2", "spare", 13335)];
+        planner.record_session_traffic("s1", 3_500.0);
+
+        let recs = planner.recommend(&ixps, &sessions, &PortFeeCostModel::default());
+        assert!(recs.iter().all(|r| r.port_id == "busy"));
+        assert!(recs.iter().any(|r| matches!(r.action, CapacityAction::UpgradePortSpeed { to_mbps: 100_000, .. })));
+        let preferred = recs.iter().find(|r| r.preferred).unwrap();
+        assert!(matches!(&preferred.action, CapacityAction::ShiftSessions { to_port_id, .. } if to_port_id == "spare"));
+
+        // Backbone costing adds inter-PoP transport and enforces the budget
+        let mut optimizer = sase_backbone::cost_optimizer::CostOptimizer::new();
+        optimizer.set_budget(rust_decimal::Decimal::from(100));
+        let recs = planner.recommend(&ixps, &sessions, &optimizer);
+        let shift = recs.iter().find(|r| matches!(r.action, CapacityAction::ShiftSessions { .. })).unwrap();
+        assert!(shift.cost.monthly_delta > 0.0);
+        assert!(!shift.cost.within_budget);
+        assert!(recs.iter().all(|r| !r.preferred));
+    }
+}
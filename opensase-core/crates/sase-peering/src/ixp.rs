@@ -264,7 +264,7 @@ mod tests {
             pop_name: "fra1".to_string(),
             speed_mbps: 10000,
             ipv4_address: Some("80.81.192.100".parse().unwrap()),
-            ipv6_address: Some("2001:7f8::65100".parse().unwrap()),
+            ipv6_address: Some("2001:7f8::fe4c:0:1".parse().unwrap()),
             vlan_id: 100,
             status: IxpConnectionStatus::Active,
             monthly_cost: 3000.0,
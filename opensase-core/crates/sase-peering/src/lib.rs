@@ -14,6 +14,7 @@ pub mod looking_glass;
 pub mod api;
 pub mod manager;
 pub mod rpki;
+pub mod capacity;
 
 pub use ixp::*;
 pub use peeringdb::*;
@@ -26,6 +27,7 @@ pub use looking_glass::*;
 pub use api::*;
 pub use manager::*;
 pub use rpki::*;
+pub use capacity::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -104,7 +104,7 @@ pub struct PeeringManager {
 impl PeeringManager {
     pub fn new(our_asn: u32) -> Self {
         Self {
-            peeringdb: PeeringDbClient::new(),
+            peeringdb: PeeringDbClient::new(None),
             our_asn,
             our_ixp_ports: HashMap::new(),
             sessions: SessionManager::new(),
@@ -140,19 +140,20 @@ impl PeeringManager {
 
         // Build candidate list
         for (asn, ixps) in seen_asns {
-            let network = match self.peeringdb.get_network(asn).await {
+            let network = match self.peeringdb.get_network_record(asn).await {
                 Ok(n) => n,
                 Err(_) => continue,
             };
 
-            let net_type: NetworkType = network.info_type.parse().unwrap_or(NetworkType::Other);
+            let net_type: NetworkType = network.info_type.as_deref().unwrap_or_default()
+                .parse().unwrap_or(NetworkType::Other);
 
             // Focus on ISPs and content networks
             if net_type != NetworkType::Nsp && net_type != NetworkType::Content {
                 continue;
             }
 
-            let policy: PeeringPolicy = network.policy_general.parse()
+            let policy: PeeringPolicy = network.policy_general.as_deref().unwrap_or_default().parse()
                 .unwrap_or(PeeringPolicy::RequiredNoInfo);
 
             // Skip restrictive networks
@@ -184,7 +185,7 @@ impl PeeringManager {
     /// Estimate traffic value for a network
     fn estimate_traffic_value(&self, network: &crate::peeringdb::PdbNetwork) -> TrafficEstimate {
         // Estimate based on network size and type
-        let base_traffic = match network.info_traffic.as_str() {
+        let base_traffic = match network.info_traffic.as_deref().unwrap_or_default() {
             "0-20Mbps" => 0.01,
             "20-100Mbps" => 0.05,
             "100-1000Mbps" => 0.5,
@@ -200,7 +201,7 @@ impl PeeringManager {
         };
 
         // Estimate ratio
-        let ratio = match network.info_ratio.as_str() {
+        let ratio = match network.info_ratio.as_deref().unwrap_or_default() {
             "Balanced" => 1.0,
             "Heavy Inbound" => 0.3,
             "Heavy Outbound" => 3.0,
@@ -208,7 +209,7 @@ impl PeeringManager {
         };
 
         // Content/CDN networks bring more inbound
-        let type_factor = match network.info_type.as_str() {
+        let type_factor = match network.info_type.as_deref().unwrap_or_default() {
             "Content" => 2.0,
             "NSP" => 1.5,
             _ => 1.0,
@@ -227,17 +228,17 @@ impl PeeringManager {
         let mut priority = traffic.value_score;
 
         // Boost for open peering policy
-        if network.policy_general == "Open" {
+        if network.policy_general.as_deref() == Some("Open") {
             priority += 20;
         }
 
         // Boost for content/CDN networks (reduce latency)
-        if network.info_type == "Content" {
+        if network.info_type.as_deref() == Some("Content") {
             priority += 30;
         }
 
         // Boost for large ISPs
-        if network.info_type == "NSP" {
+        if network.info_type.as_deref() == Some("NSP") {
             priority += 15;
         }
 
@@ -58,9 +58,11 @@ pub struct PdbNetwork {
     pub website: Option<String>,
     pub looking_glass: Option<String>,
     pub policy_general: Option<String>,
+    pub policy_url: Option<String>,
     pub info_prefixes4: Option<u32>,
     pub info_prefixes6: Option<u32>,
     pub info_ratio: Option<String>,
+    pub info_traffic: Option<String>,
     pub info_type: Option<String>,
 }
 
@@ -123,9 +125,14 @@ impl PeeringDbClient {
 
     /// Get network by ASN
     pub async fn get_network(&self, asn: u32) -> Result<PeerNetwork> {
+        self.get_network_record(asn).await.map(|n| self.convert_network(&n))
+    }
+
+    /// Get a network's raw PeeringDB record
+    pub async fn get_network_record(&self, asn: u32) -> Result<PdbNetwork> {
         let data = self.fetch_list::<PdbNetwork>(&format!("net?asn={}", asn)).await?;
-        data.first()
-            .map(|n| self.convert_network(n))
+        data.into_iter()
+            .next()
             .ok_or_else(|| PeeringDbError::NotFound(format!("ASN {}", asn)))
     }
 
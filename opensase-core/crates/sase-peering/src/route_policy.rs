@@ -170,7 +170,7 @@ impl RoutePolicy {
             filter.push_str(&format!(
                 "  if net ~ [ {}{} ] then {};\n",
                 pf.prefix, 
-                if range.is_empty() { "" } else { &format!("+{}", range) },
+                if range.is_empty() { String::new() } else { format!("+{}", range) },
                 action
             ));
         }
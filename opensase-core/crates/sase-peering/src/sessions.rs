@@ -238,7 +238,7 @@ noc@opensase.io
     }
 
     /// Find candidate peers to request peering with
-    pub fn find_peering_candidates(&self, available_peers: &[PeerNetwork]) -> Vec<&PeerNetwork> {
+    pub fn find_peering_candidates<'a>(&self, available_peers: &'a [PeerNetwork]) -> Vec<&'a PeerNetwork> {
         let existing_asns: std::collections::HashSet<u32> = self.sessions.values()
             .map(|s| s.peer_asn)
             .collect();
@@ -148,6 +148,60 @@ impl ContainerManager {
         Ok(())
     }
     
+    /// Read a file out of the container (completed downloads)
+    pub async fn read_file(&self, container_id: &str, path: &str) -> Result<Vec<u8>, String> {
+        let output = Command::new("docker")
+            .args(["exec", container_id, "cat", path])
+            .output()
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        
+        if !output.status.success() {
+            return Err(format!("Failed to read {}: {}", path, String::from_utf8_lossy(&output.stderr)));
+        }
+        
+        Ok(output.stdout)
+    }
+    
+    /// Write a file into the container (released uploads)
+    pub async fn write_file(&self, container_id: &str, path: &str, data: &[u8]) -> Result<(), String> {
+        use tokio::io::AsyncWriteExt;
+        
+        let mut child = Command::new("docker")
+            .args(["exec", "-i", container_id, "sh", "-c", "cat > \"$0\"", path])
+            .stdin(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(data).await
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        }
+        
+        let output = child.wait_with_output()
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        if !output.status.success() {
+            return Err(format!("Failed to write {}: {}", path, String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(())
+    }
+    
+    /// Remove a file from the container
+    pub async fn remove_file(&self, container_id: &str, path: &str) -> Result<(), String> {
+        let output = Command::new("docker")
+            .args(["exec", container_id, "rm", "-f", path])
+            .output()
+            .await
+            .map_err(|e| format!("Failed to remove {}: {}", path, e))?;
+        
+        if !output.status.success() {
+            warn!("Failed to remove {}: {}", path, String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(())
+    }
+    
     /// Take screenshot
     pub async fn screenshot(&self, container_id: &str) -> Result<Vec<u8>, String> {
         // Execute screenshot command in container
@@ -203,6 +257,20 @@ impl ContainerManager {
             "-e".to_string(), format!("DISPLAY_HEIGHT={}", config.viewport.height),
         ];
         
+        // Chromium saves downloads to a size-capped tmpfs the gateway pulls from
+        if config.downloads_enabled {
+            args.push("--tmpfs".to_string());
+            args.push(format!("{}:rw,noexec,nosuid,size=128m", crate::download::DOWNLOAD_DIR));
+            args.push("-e".to_string());
+            args.push(format!("DOWNLOAD_DIR={}", crate::download::DOWNLOAD_DIR));
+        }
+        
+        // Released uploads are staged here before being attached to the page
+        if config.uploads_enabled {
+            args.push("--tmpfs".to_string());
+            args.push(format!("{}:rw,noexec,nosuid,size=128m", crate::upload::UPLOAD_DIR));
+        }
+        
         // Add initial URL if specified
         if let Some(url) = &config.initial_url {
             args.push("-e".to_string());
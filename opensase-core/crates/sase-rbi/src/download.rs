@@ -1,14 +1,37 @@
 //! Download Handling
 //!
 //! File download scanning and isolation.
+//!
+//! Chromium in the container saves downloads to [`DOWNLOAD_DIR`]; the
+//! gateway follows them through CDP `Browser.download*` events. Completed
+//! files are pulled out of the container and run through the pipeline:
+//!
+//! ```text
+//! intercept ─► size/type policy ─► AV scan ─► CDR ─► storage ─► deliver
+//!                    │                │        │
+//!                    └── blocked ◄────┴────────┘        (audit record)
+//! ```
 
+use crate::gateway::{FileSanitizer, FileType};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Directory Chromium saves downloads to inside the container
+pub const DOWNLOAD_DIR: &str = "/downloads";
 
 /// Download manager with malware scanning
 pub struct DownloadManager {
     config: DownloadConfig,
     scanner: MalwareScanner,
+    sanitizer: FileSanitizer,
     pending: dashmap::DashMap<String, PendingDownload>,
+    /// Chromium download GUID -> download ID
+    guids: dashmap::DashMap<String, String>,
+    audit: parking_lot::RwLock<Vec<DownloadAudit>>,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +48,14 @@ pub struct DownloadConfig {
     pub sandbox_executables: bool,
     /// Storage path
     pub storage_path: PathBuf,
+    /// AV engine
+    pub scan_engine: ScanEngine,
+    /// Give up on the AV engine after this long (the file is blocked)
+    pub scan_timeout: Duration,
+    /// Per file type handling
+    pub type_rules: HashMap<FileCategory, FileTypeRule>,
+    /// Audit records kept in memory
+    pub audit_retention: usize,
 }
 
 impl Default for DownloadConfig {
@@ -40,10 +71,106 @@ impl Default for DownloadConfig {
             malware_scanning: true,
             sandbox_executables: true,
             storage_path: PathBuf::from("/var/lib/osbi/downloads"),
+            scan_engine: ScanEngine::Clamd { addr: "127.0.0.1:3310".to_string() },
+            scan_timeout: Duration::from_secs(30),
+            type_rules: default_type_rules(),
+            audit_retention: 10_000,
         }
     }
 }
 
+impl DownloadConfig {
+    /// Rule for a file category (unlisted categories are sanitized)
+    pub fn rule(&self, category: FileCategory) -> FileTypeRule {
+        self.type_rules.get(&category).copied().unwrap_or(FileTypeRule {
+            action: FileAction::Sanitize,
+            max_size: None,
+        })
+    }
+}
+
+/// AV engine used for download scans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanEngine {
+    /// Built-in signatures only (EICAR)
+    Signature,
+    /// clamd over TCP (INSTREAM)
+    Clamd { addr: String },
+}
+
+/// Coarse file type used for download policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum FileCategory {
+    Pdf,
+    Office,
+    Image,
+    Archive,
+    Executable,
+    Script,
+    Unknown,
+}
+
+impl From<&FileType> for FileCategory {
+    fn from(file_type: &FileType) -> Self {
+        match file_type {
+            FileType::Pdf => Self::Pdf,
+            FileType::Office(_) => Self::Office,
+            FileType::Image(_) => Self::Image,
+            FileType::Archive => Self::Archive,
+            FileType::Executable => Self::Executable,
+            FileType::Script => Self::Script,
+            FileType::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl FileCategory {
+    fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Office => "application/vnd.openxmlformats-officedocument",
+            Self::Image => "image/*",
+            Self::Archive => "application/zip",
+            Self::Executable => "application/x-executable",
+            Self::Script => "text/x-script",
+            Self::Unknown => "application/octet-stream",
+        }
+    }
+}
+
+/// Handling for one file category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileTypeRule {
+    pub action: FileAction,
+    /// Overrides `max_file_size` for this category
+    pub max_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FileAction {
+    /// Deliver the original after a clean scan
+    Allow,
+    /// Deliver the CDR-reconstructed file after a clean scan
+    Sanitize,
+    /// Never deliver
+    Block,
+}
+
+fn default_type_rules() -> HashMap<FileCategory, FileTypeRule> {
+    const MB: u64 = 1024 * 1024;
+    let rule = |action, max_size| FileTypeRule { action, max_size };
+    
+    HashMap::from([
+        (FileCategory::Pdf, rule(FileAction::Sanitize, Some(50 * MB))),
+        (FileCategory::Office, rule(FileAction::Sanitize, Some(50 * MB))),
+        (FileCategory::Image, rule(FileAction::Sanitize, Some(20 * MB))),
+        (FileCategory::Archive, rule(FileAction::Sanitize, None)),
+        (FileCategory::Executable, rule(FileAction::Block, None)),
+        (FileCategory::Script, rule(FileAction::Block, None)),
+        (FileCategory::Unknown, rule(FileAction::Sanitize, Some(10 * MB))),
+    ])
+}
+
 #[derive(Debug, Clone)]
 pub struct PendingDownload {
     pub id: String,
@@ -62,7 +189,9 @@ pub enum DownloadStatus {
     Pending,
     Downloading,
     Scanning,
+    Sanitizing,
     Ready,
+    Delivered,
     Blocked,
     Expired,
 }
@@ -75,14 +204,14 @@ pub struct ScanResult {
     pub scanner_version: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ThreatInfo {
     pub name: String,
     pub category: ThreatCategory,
     pub severity: ThreatSeverity,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub enum ThreatCategory {
     Virus,
     Trojan,
@@ -94,7 +223,7 @@ pub enum ThreatCategory {
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub enum ThreatSeverity {
     Low,
     Medium,
@@ -102,12 +231,152 @@ pub enum ThreatSeverity {
     Critical,
 }
 
+// =============================================================================
+// Browser Interception
+// =============================================================================
+
+/// Chromium download lifecycle event
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserDownloadEvent {
+    /// `Browser.downloadWillBegin`
+    WillBegin {
+        guid: String,
+        url: String,
+        suggested_filename: String,
+    },
+    /// `Browser.downloadProgress`
+    Progress {
+        guid: String,
+        total_bytes: u64,
+        received_bytes: u64,
+        state: BrowserDownloadState,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserDownloadState {
+    InProgress,
+    Completed,
+    Canceled,
+}
+
+impl BrowserDownloadEvent {
+    /// Parse a CDP event message
+    pub fn from_cdp(message: &serde_json::Value) -> Option<Self> {
+        let params = message.get("params")?;
+        let guid = params.get("guid")?.as_str()?.to_string();
+        
+        match message.get("method")?.as_str()? {
+            "Browser.downloadWillBegin" => Some(Self::WillBegin {
+                guid,
+                url: params.get("url")?.as_str()?.to_string(),
+                suggested_filename: params.get("suggestedFilename")?.as_str()?.to_string(),
+            }),
+            "Browser.downloadProgress" => Some(Self::Progress {
+                guid,
+                total_bytes: params.get("totalBytes").and_then(|v| v.as_f64()).unwrap_or(0.0) as u64,
+                received_bytes: params.get("receivedBytes").and_then(|v| v.as_f64()).unwrap_or(0.0) as u64,
+                state: match params.get("state")?.as_str()? {
+                    "completed" => BrowserDownloadState::Completed,
+                    "canceled" => BrowserDownloadState::Canceled,
+                    _ => BrowserDownloadState::InProgress,
+                },
+            }),
+            _ => None,
+        }
+    }
+    
+    /// CDP command that routes downloads into [`DOWNLOAD_DIR`] named by GUID
+    pub fn set_download_behavior() -> serde_json::Value {
+        serde_json::json!({
+            "method": "Browser.setDownloadBehavior",
+            "params": {
+                "behavior": "allowAndName",
+                "downloadPath": DOWNLOAD_DIR,
+                "eventsEnabled": true,
+            }
+        })
+    }
+    
+    /// CDP command that aborts a download the policy rejected
+    pub fn cancel(guid: &str) -> serde_json::Value {
+        serde_json::json!({
+            "method": "Browser.cancelDownload",
+            "params": { "guid": guid }
+        })
+    }
+}
+
+/// Download finished in the container and ready to be pulled out
+#[derive(Debug, Clone)]
+pub struct CompletedDownload {
+    pub download_id: String,
+    /// Path of the file inside the container
+    pub container_path: String,
+}
+
+// =============================================================================
+// Audit
+// =============================================================================
+
+/// Outcome of the scanning pipeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum DownloadVerdict {
+    /// Original delivered after a clean scan
+    Clean,
+    /// CDR-reconstructed file delivered
+    Sanitized,
+    /// Not delivered
+    Blocked { reason: String },
+}
+
+impl DownloadVerdict {
+    pub fn is_blocked(&self) -> bool {
+        matches!(self, Self::Blocked { .. })
+    }
+}
+
+/// Audit record for a scanned download
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadAudit {
+    pub download_id: String,
+    pub session_id: String,
+    pub url: String,
+    pub filename: String,
+    pub category: FileCategory,
+    pub action: FileAction,
+    pub verdict: DownloadVerdict,
+    pub original_size: u64,
+    pub original_sha256: String,
+    pub delivered_filename: Option<String>,
+    pub delivered_size: Option<u64>,
+    pub delivered_sha256: Option<String>,
+    pub threats: Vec<ThreatInfo>,
+    pub removed_content: Vec<String>,
+    pub scanner_version: String,
+    pub scan_time_ms: u64,
+    pub processed_at: chrono::DateTime<chrono::Utc>,
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// File handed to the user
+#[derive(Debug, Clone)]
+pub struct DeliveredFile {
+    pub download_id: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
 impl DownloadManager {
     pub fn new(config: DownloadConfig) -> Self {
         Self {
+            scanner: MalwareScanner::new(config.scan_engine.clone(), config.scan_timeout),
+            sanitizer: FileSanitizer::new(),
             config,
-            scanner: MalwareScanner::new(),
             pending: dashmap::DashMap::new(),
+            guids: dashmap::DashMap::new(),
+            audit: parking_lot::RwLock::new(Vec::new()),
         }
     }
     
@@ -126,7 +395,7 @@ impl DownloadManager {
         }
         
         // Check extension
-        let ext = filename.split('.').last().unwrap_or("").to_lowercase();
+        let ext = filename.split('.').next_back().unwrap_or("").to_lowercase();
         
         if self.config.blocked_extensions.contains(&ext) {
             return Err(DownloadError::BlockedType(ext));
@@ -156,23 +425,192 @@ impl DownloadManager {
         Ok(download_id)
     }
     
+    /// Track a browser download. Returns the file to pull from the container
+    /// once Chromium has finished writing it; an error means the download
+    /// should be cancelled in the browser.
+    pub async fn intercept(
+        &self,
+        session_id: &str,
+        event: &BrowserDownloadEvent,
+    ) -> Result<Option<CompletedDownload>, DownloadError> {
+        match event {
+            BrowserDownloadEvent::WillBegin { guid, url, suggested_filename } => {
+                let download_id = self
+                    .start_download(session_id, url, suggested_filename, 0, "application/octet-stream")
+                    .await?;
+                self.guids.insert(guid.clone(), download_id);
+                Ok(None)
+            }
+            BrowserDownloadEvent::Progress { guid, total_bytes, received_bytes, state } => {
+                let download_id = self.guids.get(guid)
+                    .map(|id| id.clone())
+                    .ok_or(DownloadError::NotFound)?;
+                
+                match state {
+                    BrowserDownloadState::Canceled => {
+                        self.guids.remove(guid);
+                        self.pending.remove(&download_id);
+                        Ok(None)
+                    }
+                    BrowserDownloadState::InProgress => {
+                        let size = (*total_bytes).max(*received_bytes);
+                        if size > self.config.max_file_size {
+                            self.guids.remove(guid);
+                            self.pending.remove(&download_id);
+                            return Err(DownloadError::TooLarge(size, self.config.max_file_size));
+                        }
+                        if let Some(mut download) = self.pending.get_mut(&download_id) {
+                            download.size = size;
+                            download.status = DownloadStatus::Downloading;
+                        }
+                        Ok(None)
+                    }
+                    BrowserDownloadState::Completed => {
+                        self.guids.remove(guid);
+                        Ok(Some(CompletedDownload {
+                            download_id,
+                            container_path: format!("{}/{}", DOWNLOAD_DIR, guid),
+                        }))
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Run a downloaded file through policy, AV scan and CDR, and store
+    /// the deliverable copy. Blocked files produce an audit record, not an error.
+    pub async fn process(&self, download_id: &str, data: Vec<u8>) -> Result<DownloadAudit, DownloadError> {
+        let download = self.get_status(download_id).ok_or(DownloadError::NotFound)?;
+        
+        let file_type = self.sanitizer.detect_type(&data);
+        let category = FileCategory::from(&file_type);
+        let rule = self.config.rule(category);
+        let size = data.len() as u64;
+        
+        let mut audit = DownloadAudit {
+            download_id: download.id.clone(),
+            session_id: download.session_id.clone(),
+            url: download.url.clone(),
+            filename: download.filename.clone(),
+            category,
+            action: rule.action,
+            verdict: DownloadVerdict::Clean,
+            original_size: size,
+            original_sha256: sha256_hex(&data),
+            delivered_filename: None,
+            delivered_size: None,
+            delivered_sha256: None,
+            threats: Vec::new(),
+            removed_content: Vec::new(),
+            scanner_version: String::new(),
+            scan_time_ms: 0,
+            processed_at: chrono::Utc::now(),
+            delivered_at: None,
+        };
+        
+        // Policy by content type and size
+        let max_size = rule.max_size.unwrap_or(self.config.max_file_size).min(self.config.max_file_size);
+        if rule.action == FileAction::Block {
+            return Ok(self.block(audit, None, format!("{:?} files are not allowed", category)));
+        }
+        if size > max_size {
+            return Ok(self.block(audit, None, DownloadError::TooLarge(size, max_size).to_string()));
+        }
+        
+        // AV scan (fails closed)
+        if self.config.malware_scanning {
+            self.set_status(download_id, DownloadStatus::Scanning);
+            let result = match self.scanner.scan(&data, &download.filename).await {
+                Ok(result) => result,
+                Err(e) => return Ok(self.block(audit, None, e.to_string())),
+            };
+            audit.scanner_version = result.scanner_version.clone();
+            audit.scan_time_ms = result.scan_time_ms;
+            audit.threats = result.threats.clone();
+            
+            if !result.clean {
+                let reason = format!("Malware detected: {}", result.threats.iter()
+                    .map(|t| t.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "));
+                return Ok(self.block(audit, Some(result), reason));
+            }
+            if let Some(mut pending) = self.pending.get_mut(download_id) {
+                pending.scan_result = Some(result);
+            }
+        }
+        
+        // CDR
+        let (filename, delivered) = match rule.action {
+            FileAction::Sanitize => {
+                self.set_status(download_id, DownloadStatus::Sanitizing);
+                match self.sanitizer.sanitize(&file_type, &data, &download.filename).await {
+                    Ok(sanitized) => {
+                        audit.verdict = DownloadVerdict::Sanitized;
+                        audit.removed_content = sanitized.removed_threats;
+                        (sanitized.filename, sanitized.data)
+                    }
+                    Err(e) => return Ok(self.block(audit, None, e.to_string())),
+                }
+            }
+            _ => (download.filename.clone(), data),
+        };
+        
+        // Store for delivery
+        let filename = sanitize_filename(&filename);
+        let dir = self.config.storage_path.join(&download.id);
+        tokio::fs::create_dir_all(&dir).await
+            .map_err(|e| DownloadError::Storage(e.to_string()))?;
+        tokio::fs::write(dir.join(&filename), &delivered).await
+            .map_err(|e| DownloadError::Storage(e.to_string()))?;
+        
+        audit.delivered_size = Some(delivered.len() as u64);
+        audit.delivered_sha256 = Some(sha256_hex(&delivered));
+        audit.delivered_filename = Some(filename.clone());
+        
+        if let Some(mut pending) = self.pending.get_mut(download_id) {
+            pending.filename = filename;
+            pending.size = delivered.len() as u64;
+            pending.mime_type = category.mime_type().to_string();
+            pending.status = DownloadStatus::Ready;
+        }
+        
+        tracing::info!(
+            "Download {} for session {} {:?} ({:?})",
+            download_id, download.session_id, audit.verdict, category
+        );
+        self.record_audit(audit.clone());
+        Ok(audit)
+    }
+    
     /// Scan downloaded file
     pub async fn scan(&self, download_id: &str, data: &[u8]) -> Result<ScanResult, DownloadError> {
-        let mut download = self.pending.get_mut(download_id)
+        let filename = self.pending.get(download_id)
+            .map(|d| d.filename.clone())
             .ok_or(DownloadError::NotFound)?;
         
-        download.status = DownloadStatus::Scanning;
+        self.set_status(download_id, DownloadStatus::Scanning);
         
-        let result = self.scanner.scan(data, &download.filename).await;
+        let result = self.scanner.scan(data, &filename).await;
         
-        download.scan_result = Some(result.clone());
-        download.status = if result.clean {
-            DownloadStatus::Ready
-        } else {
-            DownloadStatus::Blocked
-        };
+        let mut download = self.pending.get_mut(download_id)
+            .ok_or(DownloadError::NotFound)?;
         
-        Ok(result)
+        match result {
+            Ok(result) => {
+                download.scan_result = Some(result.clone());
+                download.status = if result.clean {
+                    DownloadStatus::Ready
+                } else {
+                    DownloadStatus::Blocked
+                };
+                Ok(result)
+            }
+            Err(e) => {
+                download.status = DownloadStatus::Blocked;
+                Err(e)
+            }
+        }
     }
     
     /// Get download status
@@ -185,7 +623,7 @@ impl DownloadManager {
         let download = self.pending.get(download_id)
             .ok_or(DownloadError::NotFound)?;
         
-        if download.status != DownloadStatus::Ready {
+        if !matches!(download.status, DownloadStatus::Ready | DownloadStatus::Delivered) {
             return Err(DownloadError::NotReady);
         }
         
@@ -196,11 +634,83 @@ impl DownloadManager {
         Ok(path)
     }
     
+    /// Hand the stored (scanned / sanitized) file to the user
+    pub async fn deliver(&self, download_id: &str) -> Result<DeliveredFile, DownloadError> {
+        let path = self.approve(download_id)?;
+        let data = tokio::fs::read(&path).await
+            .map_err(|e| DownloadError::Storage(e.to_string()))?;
+        
+        let mut download = self.pending.get_mut(download_id)
+            .ok_or(DownloadError::NotFound)?;
+        download.status = DownloadStatus::Delivered;
+        
+        let now = chrono::Utc::now();
+        if let Some(record) = self.audit.write().iter_mut().rev().find(|a| a.download_id == download_id) {
+            record.delivered_at.get_or_insert(now);
+        }
+        
+        Ok(DeliveredFile {
+            download_id: download_id.to_string(),
+            filename: download.filename.clone(),
+            mime_type: download.mime_type.clone(),
+            data,
+        })
+    }
+    
+    /// Session that owns a download
+    pub fn session_of(&self, download_id: &str) -> Option<String> {
+        self.pending.get(download_id).map(|d| d.session_id.clone())
+    }
+    
+    /// Audit records for a session
+    pub fn audit_log(&self, session_id: &str) -> Vec<DownloadAudit> {
+        self.audit.read().iter()
+            .filter(|a| a.session_id == session_id)
+            .cloned()
+            .collect()
+    }
+    
     /// Clean up expired downloads
     pub fn cleanup(&self) {
         let cutoff = chrono::Utc::now() - chrono::Duration::hours(24);
+        let storage = &self.config.storage_path;
         
-        self.pending.retain(|_, d| d.started_at > cutoff);
+        self.pending.retain(|id, d| {
+            let keep = d.started_at > cutoff;
+            if !keep {
+                let _ = std::fs::remove_dir_all(storage.join(id));
+            }
+            keep
+        });
+    }
+    
+    fn block(&self, mut audit: DownloadAudit, scan: Option<ScanResult>, reason: String) -> DownloadAudit {
+        if let Some(mut download) = self.pending.get_mut(&audit.download_id) {
+            download.status = DownloadStatus::Blocked;
+            if scan.is_some() {
+                download.scan_result = scan;
+            }
+        }
+        
+        tracing::warn!("Blocked download {} ({}): {}", audit.download_id, audit.filename, reason);
+        audit.verdict = DownloadVerdict::Blocked { reason };
+        self.record_audit(audit.clone());
+        audit
+    }
+    
+    fn set_status(&self, download_id: &str, status: DownloadStatus) {
+        if let Some(mut download) = self.pending.get_mut(download_id) {
+            download.status = status;
+        }
+    }
+    
+    fn record_audit(&self, audit: DownloadAudit) {
+        let mut log = self.audit.write();
+        log.push(audit);
+        if log.len() > self.config.audit_retention {
+            let excess = log.len() - self.config.audit_retention;
+            log.drain(..excess);
+        }
     }
 }
 
@@ -212,42 +722,99 @@ impl Default for DownloadManager {
 
 /// Malware scanner interface
 struct MalwareScanner {
-    engine: String,
+    engine: ScanEngine,
+    timeout: Duration,
 }
 
 impl MalwareScanner {
-    fn new() -> Self {
-        Self {
-            engine: "ClamAV".to_string(),
-        }
+    fn new(engine: ScanEngine, timeout: Duration) -> Self {
+        Self { engine, timeout }
     }
     
-    async fn scan(&self, data: &[u8], filename: &str) -> ScanResult {
+    async fn scan(&self, data: &[u8], filename: &str) -> Result<ScanResult, DownloadError> {
         let start = std::time::Instant::now();
         
-        // In production, this would call actual AV engine
-        // Check for EICAR test string
-        let is_eicar = std::str::from_utf8(data)
-            .map(|s| s.contains("EICAR-STANDARD-ANTIVIRUS-TEST-FILE"))
-            .unwrap_or(false);
-        
-        ScanResult {
-            clean: !is_eicar,
-            threats: if is_eicar {
-                vec![ThreatInfo {
-                    name: "EICAR-Test-File".to_string(),
-                    category: ThreatCategory::Virus,
-                    severity: ThreatSeverity::Low,
-                }]
-            } else {
-                Vec::new()
-            },
+        // Built-in signature check runs regardless of engine
+        let is_eicar = String::from_utf8_lossy(data)
+            .contains("EICAR-STANDARD-ANTIVIRUS-TEST-FILE");
+        
+        let (mut threats, scanner_version) = match &self.engine {
+            ScanEngine::Signature => (Vec::new(), "builtin-signatures".to_string()),
+            ScanEngine::Clamd { addr } => {
+                let names = tokio::time::timeout(self.timeout, clamd_instream(addr, data))
+                    .await
+                    .map_err(|_| DownloadError::ScanFailed(format!("clamd timed out scanning {}", filename)))??;
+                (names.iter().map(|n| threat_from_signature(n)).collect(), "ClamAV".to_string())
+            }
+        };
+        
+        if is_eicar && threats.is_empty() {
+            threats.push(ThreatInfo {
+                name: "EICAR-Test-File".to_string(),
+                category: ThreatCategory::Virus,
+                severity: ThreatSeverity::Low,
+            });
+        }
+        
+        Ok(ScanResult {
+            clean: threats.is_empty(),
+            threats,
             scan_time_ms: start.elapsed().as_millis() as u64,
-            scanner_version: "ClamAV 1.0".to_string(),
+            scanner_version,
+        })
+    }
+}
+
+/// Scan via clamd `INSTREAM`; returns detected signature names
+async fn clamd_instream(addr: &str, data: &[u8]) -> Result<Vec<String>, DownloadError> {
+    let io_err = |e: std::io::Error| DownloadError::ScanFailed(format!("clamd {}: {}", addr, e));
+    
+    let mut stream = tokio::net::TcpStream::connect(addr).await.map_err(io_err)?;
+    stream.write_all(b"zINSTREAM\0").await.map_err(io_err)?;
+    for chunk in data.chunks(64 * 1024) {
+        stream.write_all(&(chunk.len() as u32).to_be_bytes()).await.map_err(io_err)?;
+        stream.write_all(chunk).await.map_err(io_err)?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await.map_err(io_err)?;
+    
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await.map_err(io_err)?;
+    let reply = String::from_utf8_lossy(&reply);
+    let reply = reply.trim_end_matches('\0').trim();
+    
+    // "stream: OK" | "stream: <signature> FOUND" | "<message> ERROR"
+    match reply.strip_prefix("stream: ") {
+        Some("OK") => Ok(Vec::new()),
+        Some(found) if found.ends_with(" FOUND") => {
+            Ok(vec![found.trim_end_matches(" FOUND").to_string()])
         }
+        _ => Err(DownloadError::ScanFailed(reply.to_string())),
     }
 }
 
+fn threat_from_signature(name: &str) -> ThreatInfo {
+    let lower = name.to_lowercase();
+    let (category, severity) = if lower.contains("ransom") {
+        (ThreatCategory::Ransomware, ThreatSeverity::Critical)
+    } else if lower.contains("trojan") {
+        (ThreatCategory::Trojan, ThreatSeverity::High)
+    } else if lower.contains("exploit") || lower.contains("cve") {
+        (ThreatCategory::Exploit, ThreatSeverity::High)
+    } else if lower.contains("phish") {
+        (ThreatCategory::Phishing, ThreatSeverity::Medium)
+    } else if lower.contains("spy") {
+        (ThreatCategory::Spyware, ThreatSeverity::High)
+    } else if lower.contains("adware") || lower.contains("pua") {
+        (ThreatCategory::Adware, ThreatSeverity::Low)
+    } else if lower.contains("eicar") {
+        (ThreatCategory::Virus, ThreatSeverity::Low)
+    } else {
+        (ThreatCategory::Unknown, ThreatSeverity::Medium)
+    };
+    
+    ThreatInfo { name: name.to_string(), category, severity }
+}
+
 #[derive(Debug, Clone)]
 pub enum DownloadError {
     TooLarge(u64, u64),
@@ -256,6 +823,7 @@ pub enum DownloadError {
     NotFound,
     NotReady,
     ScanFailed(String),
+    Storage(String),
 }
 
 impl std::fmt::Display for DownloadError {
@@ -267,6 +835,7 @@ impl std::fmt::Display for DownloadError {
             Self::NotFound => write!(f, "Download not found"),
             Self::NotReady => write!(f, "Download not ready"),
             Self::ScanFailed(e) => write!(f, "Scan failed: {}", e),
+            Self::Storage(e) => write!(f, "Download storage error: {}", e),
         }
     }
 }
@@ -276,3 +845,65 @@ fn sanitize_filename(name: &str) -> String {
         .filter(|c| c.is_alphanumeric() || *c == '.' || *c == '-' || *c == '_')
         .collect()
 }
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn manager() -> DownloadManager {
+        DownloadManager::new(DownloadConfig {
+            scan_engine: ScanEngine::Signature,
+            storage_path: std::env::temp_dir().join(format!("osbi-dl-{}", uuid::Uuid::new_v4())),
+            ..Default::default()
+        })
+    }
+    
+    async fn complete(manager: &DownloadManager, filename: &str) -> CompletedDownload {
+        let begin = BrowserDownloadEvent::from_cdp(&serde_json::json!({
+            "method": "Browser.downloadWillBegin",
+            "params": { "frameId": "f", "guid": "g1", "url": "https://example.com/f", "suggestedFilename": filename }
+        })).unwrap();
+        assert!(manager.intercept("s1", &begin).await.unwrap().is_none());
+        
+        let done = BrowserDownloadEvent::from_cdp(&serde_json::json!({
+            "method": "Browser.downloadProgress",
+            "params": { "guid": "g1", "totalBytes": 20.0, "receivedBytes": 20.0, "state": "completed" }
+        })).unwrap();
+        manager.intercept("s1", &done).await.unwrap().unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_pdf_sanitized_and_delivered() {
+        let manager = manager();
+        let completed = complete(&manager, "report.pdf").await;
+        assert_eq!(completed.container_path, "/downloads/g1");
+        
+        let audit = manager.process(&completed.download_id, b"%PDF-1.7 body".to_vec()).await.unwrap();
+        assert_eq!(audit.verdict, DownloadVerdict::Sanitized);
+        assert_eq!(audit.category, FileCategory::Pdf);
+        
+        let file = manager.deliver(&completed.download_id).await.unwrap();
+        assert_eq!(file.filename, "report.pdf");
+        assert!(manager.audit_log("s1")[0].delivered_at.is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_malware_and_executables_blocked() {
+        let manager = manager();
+        let completed = complete(&manager, "notes.txt").await;
+        let eicar = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*".to_vec();
+        let audit = manager.process(&completed.download_id, eicar).await.unwrap();
+        assert!(audit.verdict.is_blocked());
+        assert_eq!(audit.threats.len(), 1);
+        assert!(matches!(manager.deliver(&completed.download_id).await, Err(DownloadError::NotReady)));
+        
+        let completed = complete(&manager, "setup.dat").await;
+        let audit = manager.process(&completed.download_id, b"MZ\x90\x00\x03\x00\x00\x00".to_vec()).await.unwrap();
+        assert!(audit.verdict.is_blocked());
+        assert_eq!(audit.category, FileCategory::Executable);
+    }
+}
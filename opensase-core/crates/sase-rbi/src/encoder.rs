@@ -3,6 +3,7 @@
 //! H.264/VP9/AV1 video encoding for pixel-push streaming.
 
 use crate::{VideoCodec, StreamQuality, StreamConfig, Viewport};
+use crate::transport::BitrateDecision;
 
 /// Pixel encoder for video streaming
 pub struct PixelEncoder {
@@ -178,6 +179,13 @@ impl PixelEncoder {
         self.last_keyframe = 0;
     }
     
+    /// Apply adapted rate settings
+    pub fn apply(&mut self, decision: &BitrateDecision) {
+        self.config.bitrate_kbps = decision.bitrate_kbps;
+        self.config.fps = decision.fps.max(1);
+        self.config.quality = decision.quality;
+    }
+    
     /// Get encoder statistics
     pub fn get_stats(&self) -> EncoderSnapshot {
         use std::sync::atomic::Ordering;
@@ -9,6 +9,8 @@ use crate::{
 use crate::pool::{ContainerPool, PooledContainer};
 use crate::session::SessionManager;
 use crate::streaming::StreamManager;
+use crate::webrtc::{self, Fingerprint, LocalEndpoint, NegotiatedStream};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
@@ -20,6 +22,8 @@ pub struct RbiGateway {
     sessions: SessionManager,
     /// Stream manager
     streams: StreamManager,
+    /// Negotiated WebRTC streams awaiting the media plane
+    negotiated: dashmap::DashMap<String, NegotiatedStream>,
     /// File sanitizer
     sanitizer: Arc<FileSanitizer>,
     /// Gateway configuration
@@ -35,6 +39,10 @@ pub struct GatewayConfig {
     pub enable_uploads: bool,
     pub enable_clipboard: bool,
     pub enable_printing: bool,
+    /// SHA-256 fingerprint of the media plane's DTLS certificate
+    pub dtls_fingerprint: String,
+    /// UDP addresses the media plane listens on (ICE host candidates)
+    pub media_addrs: Vec<SocketAddr>,
 }
 
 impl Default for GatewayConfig {
@@ -47,6 +55,8 @@ impl Default for GatewayConfig {
             enable_uploads: true,
             enable_clipboard: true,
             enable_printing: false,
+            dtls_fingerprint: String::new(),
+            media_addrs: Vec::new(),
         }
     }
 }
@@ -79,6 +89,7 @@ impl RbiGateway {
             pool: Arc::new(ContainerPool::new(Default::default())),
             sessions: SessionManager::new(Default::default()),
             streams: StreamManager::new(),
+            negotiated: dashmap::DashMap::new(),
             sanitizer: Arc::new(FileSanitizer::new()),
             config,
         }
@@ -134,11 +145,23 @@ impl RbiGateway {
         let _rx = self.streams.create_stream(session_id, IsolationMode::PixelPush, None);
         
         // Generate SDP answer
-        let answer = self.generate_sdp_answer(sdp_offer)?;
+        let (answer, negotiated) = self.generate_sdp_answer(sdp_offer)?;
+        self.negotiated.insert(session_id.to_string(), negotiated);
         
         Ok(answer)
     }
     
+    /// Hand a negotiated stream to the media plane, which builds the
+    /// `WebRtcPeer` around its DTLS engine
+    pub fn take_negotiated_stream(&self, session_id: &str) -> Option<NegotiatedStream> {
+        self.negotiated.remove(session_id).map(|(_, n)| n)
+    }
+    
+    /// Stream manager (transport feedback, bitrate adaptation)
+    pub fn streams(&self) -> &StreamManager {
+        &self.streams
+    }
+    
     /// Handle input event from client
     pub async fn handle_input(
         &self,
@@ -161,30 +184,9 @@ impl RbiGateway {
             return Err(GatewayError::DownloadsDisabled);
         }
         
-        // Detect file type
+        // Detect file type and sanitize accordingly
         let file_type = self.sanitizer.detect_type(&file_data);
-        
-        // Sanitize based on type
-        match file_type {
-            FileType::Pdf => {
-                self.sanitizer.sanitize_pdf(&file_data, filename).await
-            }
-            FileType::Office(_) => {
-                self.sanitizer.sanitize_office(&file_data, filename).await
-            }
-            FileType::Image(_) => {
-                self.sanitizer.sanitize_image(&file_data, filename).await
-            }
-            FileType::Archive => {
-                self.sanitizer.sanitize_archive(&file_data, filename).await
-            }
-            FileType::Executable | FileType::Script => {
-                Err(GatewayError::BlockedFileType(format!("{:?}", file_type)))
-            }
-            FileType::Unknown => {
-                self.sanitizer.convert_to_safe(&file_data, filename).await
-            }
-        }
+        self.sanitizer.sanitize(&file_type, &file_data, filename).await
     }
     
     /// Handle file upload to isolated browser
@@ -244,6 +246,7 @@ impl RbiGateway {
     /// Terminate session
     pub async fn terminate_session(&self, session_id: &str) -> Result<(), GatewayError> {
         self.streams.close_stream(session_id);
+        self.negotiated.remove(session_id);
         // Release container back to pool
         Ok(())
     }
@@ -266,9 +269,19 @@ impl RbiGateway {
         Ok(())
     }
     
-    fn generate_sdp_answer(&self, offer: &str) -> Result<String, GatewayError> {
-        // Generate WebRTC SDP answer
-        Ok("v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\ns=-\r\nt=0 0\r\n".to_string())
+    fn generate_sdp_answer(&self, offer: &str) -> Result<(String, NegotiatedStream), GatewayError> {
+        if self.config.dtls_fingerprint.is_empty() || self.config.media_addrs.is_empty() {
+            return Err(GatewayError::StreamNegotiation("WebRTC media plane not configured".to_string()));
+        }
+        
+        let local = LocalEndpoint::new(
+            Fingerprint::new("sha-256", &self.config.dtls_fingerprint),
+            &self.config.media_addrs,
+        );
+        let codec = crate::StreamConfig::default().codec;
+        
+        webrtc::negotiate(offer, &local, codec)
+            .map_err(|e| GatewayError::StreamNegotiation(e.to_string()))
     }
     
     async fn get_browser_clipboard(&self, session_id: &str) -> Result<String, GatewayError> {
@@ -314,6 +327,7 @@ pub enum GatewayError {
     BlockedFileType(String),
     MaliciousFile(Vec<String>),
     SanitizationError(String),
+    StreamNegotiation(String),
 }
 
 impl std::fmt::Display for GatewayError {
@@ -328,6 +342,7 @@ impl std::fmt::Display for GatewayError {
             Self::BlockedFileType(t) => write!(f, "Blocked file type: {}", t),
             Self::MaliciousFile(threats) => write!(f, "Malicious file: {:?}", threats),
             Self::SanitizationError(e) => write!(f, "Sanitization error: {}", e),
+            Self::StreamNegotiation(e) => write!(f, "Stream negotiation failed: {}", e),
         }
     }
 }
@@ -463,6 +478,20 @@ impl FileSanitizer {
         })
     }
     
+    /// Sanitize by detected type; executables and scripts are rejected
+    pub async fn sanitize(&self, file_type: &FileType, data: &[u8], filename: &str) -> Result<SanitizedFile, GatewayError> {
+        match file_type {
+            FileType::Pdf => self.sanitize_pdf(data, filename).await,
+            FileType::Office(_) => self.sanitize_office(data, filename).await,
+            FileType::Image(_) => self.sanitize_image(data, filename).await,
+            FileType::Archive => self.sanitize_archive(data, filename).await,
+            FileType::Executable | FileType::Script => {
+                Err(GatewayError::BlockedFileType(format!("{:?}", file_type)))
+            }
+            FileType::Unknown => self.convert_to_safe(data, filename).await,
+        }
+    }
+    
     /// Sanitize PDF by removing active content
     pub async fn sanitize_pdf(&self, data: &[u8], filename: &str) -> Result<SanitizedFile, GatewayError> {
         // In production: Parse PDF, remove JavaScript, embedded files, forms
@@ -32,10 +32,15 @@ pub mod policy;
 pub mod session;
 pub mod input;
 pub mod download;
+pub mod upload;
+pub mod recording;
 pub mod swg;
 pub mod pool;
 pub mod encoder;
 pub mod gateway;
+pub mod transport;
+pub mod webrtc;
+pub mod metering;
 
 // =============================================================================
 // Session Types
@@ -86,6 +91,9 @@ pub struct SessionConfig {
     pub viewport: Viewport,
     /// Enable file downloads (with scanning)
     pub downloads_enabled: bool,
+    /// Enable file uploads (with destination policy and DLP)
+    #[serde(default = "default_uploads_enabled")]
+    pub uploads_enabled: bool,
     /// Enable clipboard (with DLP)
     pub clipboard_enabled: bool,
     /// Enable printing
@@ -104,6 +112,9 @@ pub struct SessionConfig {
     pub dlp_rules: Vec<String>,
     /// Enable session recording
     pub recording_enabled: bool,
+    /// Owning tenant (recording keys and retention); defaults to the user
+    #[serde(default)]
+    pub tenant_id: Option<String>,
 }
 
 impl Default for SessionConfig {
@@ -112,6 +123,7 @@ impl Default for SessionConfig {
             initial_url: None,
             viewport: Viewport::default(),
             downloads_enabled: true,
+            uploads_enabled: true,
             clipboard_enabled: true,
             print_enabled: false,
             timeout: Duration::from_secs(3600), // 1 hour
@@ -121,10 +133,15 @@ impl Default for SessionConfig {
             blocked_domains: Vec::new(),
             dlp_rules: Vec::new(),
             recording_enabled: false,
+            tenant_id: None,
         }
     }
 }
 
+fn default_uploads_enabled() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Viewport {
     pub width: u32,
@@ -149,6 +166,8 @@ pub struct SessionMetrics {
     pub events_received: u64,
     pub pages_visited: u64,
     pub downloads_scanned: u64,
+    #[serde(default)]
+    pub uploads_scanned: u64,
     pub threats_blocked: u64,
     pub latency_ms: f64,
     pub bandwidth_kbps: f64,
@@ -362,6 +381,10 @@ pub struct BrowserIsolationService {
     container_manager: container::ContainerManager,
     sessions: dashmap::DashMap<String, IsolationSession>,
     stream_manager: streaming::StreamManager,
+    downloads: download::DownloadManager,
+    uploads: upload::UploadManager,
+    recorder: recording::SessionRecorder,
+    meter: std::sync::Arc<metering::UsageMeter>,
 }
 
 #[derive(Debug, Clone)]
@@ -400,9 +423,80 @@ impl BrowserIsolationService {
             container_manager: container::ContainerManager::new(&config.container_image),
             sessions: dashmap::DashMap::new(),
             stream_manager: streaming::StreamManager::new(),
+            downloads: download::DownloadManager::new(download::DownloadConfig {
+                malware_scanning: config.malware_scanning,
+                ..Default::default()
+            }),
+            uploads: upload::UploadManager::default(),
+            recorder: recording::SessionRecorder::new(
+                recording::RecordingConfig::default(),
+                recording::TenantKeyring::ephemeral(),
+            ),
+            meter: std::sync::Arc::new(metering::UsageMeter::new(metering::MeteringConfig {
+                pop_location: config.pop_location.clone(),
+                ..Default::default()
+            })),
         }
     }
     
+    /// Use a recorder with persistent tenant keys and storage
+    pub fn with_recorder(mut self, recorder: recording::SessionRecorder) -> Self {
+        self.recorder = recorder;
+        self
+    }
+    
+    /// Use an upload manager with tenant upload policy
+    pub fn with_uploads(mut self, uploads: upload::UploadManager) -> Self {
+        self.uploads = uploads;
+        self
+    }
+    
+    /// Use a usage meter with custom flush interval or backlog bound
+    pub fn with_metering(mut self, meter: metering::UsageMeter) -> Self {
+        self.meter = std::sync::Arc::new(meter);
+        self
+    }
+    
+    /// Per-tenant usage meter
+    pub fn metering(&self) -> &metering::UsageMeter {
+        &self.meter
+    }
+    
+    /// Sample every live session and flush accrued usage to billing
+    pub async fn flush_usage(&self, sink: &dyn metering::UsageSink) -> metering::FlushReport {
+        let now = chrono::Utc::now();
+        for session in self.sessions.iter() {
+            self.meter.observe(&session, now);
+        }
+        self.meter.flush(sink, now).await
+    }
+    
+    /// Flush usage to billing every `flush_interval` until the service is
+    /// dropped. Billing outages are absorbed by the meter's backlog.
+    pub fn start_metering(
+        self: &std::sync::Arc<Self>,
+        sink: std::sync::Arc<dyn metering::UsageSink>,
+    ) -> tokio::task::JoinHandle<()> {
+        let service = std::sync::Arc::downgrade(self);
+        let interval = self.meter.config().flush_interval;
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(interval);
+            tick.tick().await;
+            loop {
+                tick.tick().await;
+                let Some(service) = service.upgrade() else {
+                    break;
+                };
+                service.flush_usage(sink.as_ref()).await;
+            }
+        })
+    }
+    
+    /// Recording store (replay API, retention)
+    pub fn recordings(&self) -> &recording::SessionRecorder {
+        &self.recorder
+    }
+    
     /// Create new isolation session
     pub async fn create_session(
         &self,
@@ -434,6 +528,15 @@ impl BrowserIsolationService {
             metrics: SessionMetrics::default(),
         };
         
+        if session.config.recording_enabled {
+            let tenant_id = session.config.tenant_id.as_deref().unwrap_or(user_id);
+            self.recorder.start(&session_id, tenant_id, user_id);
+            if let Some(url) = &session.config.initial_url {
+                self.record(&session_id, recording::RecordingEvent::Navigation { url: url.clone() }).await;
+            }
+        }
+        
+        self.meter.session_started(&session);
         self.sessions.insert(session_id, session.clone());
         
         Ok(session)
@@ -446,7 +549,11 @@ impl BrowserIsolationService {
     
     /// Terminate session
     pub async fn terminate_session(&self, session_id: &str) -> Result<(), String> {
+        if let Err(e) = self.recorder.stop(session_id).await {
+            tracing::warn!("Failed to finalize recording for {}: {}", session_id, e);
+        }
         if let Some((_, session)) = self.sessions.remove(session_id) {
+            self.meter.session_ended(&session, chrono::Utc::now());
             self.container_manager.destroy_container(&session.container_id).await?;
         }
         Ok(())
@@ -458,16 +565,200 @@ impl BrowserIsolationService {
         session_id: &str,
         event: InputEvent,
     ) -> Result<(), String> {
-        let session = self.sessions.get(session_id)
+        let container_id = self.sessions.get(session_id)
+            .map(|s| s.container_id.clone())
             .ok_or("Session not found")?;
         
         // Validate and sanitize input
         let sanitized = self.sanitize_input(&event)?;
+        self.record(session_id, recording::RecordingEvent::Input(sanitized.clone())).await;
         
         // Forward to container
         self.container_manager
-            .send_input(&session.container_id, sanitized)
+            .send_input(&container_id, sanitized)
+            .await
+    }
+    
+    /// Send a frame to the client, capturing keyframes and DOM changes for
+    /// recorded sessions
+    pub async fn send_frame(&self, session_id: &str, frame: streaming::StreamFrame) -> Result<(), String> {
+        match &frame {
+            streaming::StreamFrame::Video(video) if video.keyframe => {
+                self.record(session_id, recording::RecordingEvent::Keyframe {
+                    codec: video.codec,
+                    width: video.width,
+                    height: video.height,
+                    data: video.data.clone(),
+                }).await;
+            }
+            streaming::StreamFrame::Dom(update) => {
+                self.record(session_id, recording::RecordingEvent::DomUpdate(update.clone())).await;
+            }
+            _ => {}
+        }
+        
+        let bytes = match &frame {
+            streaming::StreamFrame::Video(video) => video.data.len(),
+            streaming::StreamFrame::Audio(audio) => audio.samples.len() * 2,
+            streaming::StreamFrame::Dom(update) => serde_json::to_vec(update).map(|v| v.len()).unwrap_or(0),
+            streaming::StreamFrame::Cursor(_) => 0,
+        };
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.metrics.bytes_streamed += bytes as u64;
+            session.metrics.frames_sent += 1;
+            session.last_activity = chrono::Utc::now();
+        }
+        
+        self.stream_manager.send_frame(session_id, frame)
+    }
+    
+    /// Capture a full DOM snapshot (page load) for a recorded session
+    pub async fn record_dom_snapshot(&self, session_id: &str, url: &str, elements: Vec<DomElement>) {
+        self.record(session_id, recording::RecordingEvent::DomSnapshot {
+            url: url.to_string(),
+            elements,
+        }).await;
+    }
+    
+    /// Handle a download event from the isolated browser. Completed files
+    /// are pulled out of the container and scanned/sanitized; an error means
+    /// the browser should cancel the download.
+    pub async fn handle_download_event(
+        &self,
+        session_id: &str,
+        event: download::BrowserDownloadEvent,
+    ) -> Result<Option<download::DownloadAudit>, String> {
+        let (container_id, downloads_enabled) = {
+            let session = self.sessions.get(session_id)
+                .ok_or("Session not found")?;
+            (session.container_id.clone(), session.config.downloads_enabled)
+        };
+        
+        if !downloads_enabled {
+            return Err("Downloads disabled for session".to_string());
+        }
+        
+        let completed = match self.downloads.intercept(session_id, &event).await {
+            Ok(Some(completed)) => completed,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        
+        let data = self.container_manager
+            .read_file(&container_id, &completed.container_path)
+            .await?;
+        self.container_manager.remove_file(&container_id, &completed.container_path).await?;
+        
+        let audit = self.downloads
+            .process(&completed.download_id, data)
             .await
+            .map_err(|e| e.to_string())?;
+        
+        self.record(session_id, recording::RecordingEvent::Download {
+            download_id: audit.download_id.clone(),
+            filename: audit.filename.clone(),
+            verdict: format!("{:?}", audit.verdict),
+        }).await;
+        
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.metrics.downloads_scanned += 1;
+            if !audit.threats.is_empty() {
+                session.metrics.threats_blocked += 1;
+            }
+            session.last_activity = chrono::Utc::now();
+        }
+        
+        Ok(Some(audit))
+    }
+    
+    /// Deliver a scanned download to the session's user
+    pub async fn fetch_download(
+        &self,
+        session_id: &str,
+        download_id: &str,
+    ) -> Result<download::DeliveredFile, String> {
+        if self.downloads.session_of(download_id).as_deref() != Some(session_id) {
+            return Err("Download not found".to_string());
+        }
+        self.downloads.deliver(download_id).await.map_err(|e| e.to_string())
+    }
+    
+    /// Download audit records for a session
+    pub fn download_audit(&self, session_id: &str) -> Vec<download::DownloadAudit> {
+        self.downloads.audit_log(session_id)
+    }
+    
+    /// Intercept a file the user chose for an upload on `page_url` in the
+    /// isolated page. Released uploads can be attached right away with
+    /// [`Self::attach_upload`]; held uploads once approved.
+    pub async fn handle_upload(
+        &self,
+        session_id: &str,
+        chooser: &upload::FileChooserEvent,
+        page_url: &str,
+        filename: &str,
+        data: Vec<u8>,
+    ) -> Result<upload::UploadAudit, String> {
+        let user_id = {
+            let session = self.sessions.get(session_id)
+                .ok_or("Session not found")?;
+            if !session.config.uploads_enabled {
+                return Err("Uploads disabled for session".to_string());
+            }
+            session.user_id.clone()
+        };
+        
+        let category = swg::current().categorize_url(page_url);
+        let audit = self.uploads
+            .submit(upload::UploadRequest {
+                session_id: session_id.to_string(),
+                user_id,
+                destination_url: page_url.to_string(),
+                category: Some(category),
+                backend_node_id: chooser.backend_node_id,
+                filename: filename.to_string(),
+                data,
+            })
+            .map_err(|e| e.to_string())?;
+        
+        self.record(session_id, recording::RecordingEvent::Marker {
+            label: format!("Upload {}: {:?}", audit.filename, audit.status),
+        }).await;
+        
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.metrics.uploads_scanned += 1;
+            session.last_activity = chrono::Utc::now();
+        }
+        
+        Ok(audit)
+    }
+    
+    /// Write a released upload into the container. Returns the CDP command
+    /// that attaches it to the page's file input.
+    pub async fn attach_upload(&self, session_id: &str, upload_id: &str) -> Result<serde_json::Value, String> {
+        if self.uploads.session_of(upload_id).as_deref() != Some(session_id) {
+            return Err("Upload not found".to_string());
+        }
+        let container_id = self.sessions.get(session_id)
+            .map(|s| s.container_id.clone())
+            .ok_or("Session not found")?;
+        
+        let released = self.uploads.take_released(upload_id).map_err(|e| e.to_string())?;
+        self.container_manager
+            .write_file(&container_id, &released.container_path, &released.data)
+            .await?;
+        
+        Ok(upload::FileChooserEvent::set_files(released.backend_node_id, &[released.container_path]))
+    }
+    
+    /// Upload manager (approval queue)
+    pub fn uploads(&self) -> &upload::UploadManager {
+        &self.uploads
+    }
+    
+    /// Upload audit records for a session
+    pub fn upload_audit(&self, session_id: &str) -> Vec<upload::UploadAudit> {
+        self.uploads.audit_log(session_id)
     }
     
     /// Get active session count
@@ -475,6 +766,12 @@ impl BrowserIsolationService {
         self.sessions.len()
     }
     
+    async fn record(&self, session_id: &str, event: recording::RecordingEvent) {
+        if let Err(e) = self.recorder.record(session_id, event).await {
+            tracing::warn!("Failed to record event for {}: {}", session_id, e);
+        }
+    }
+    
     fn sanitize_input(&self, event: &InputEvent) -> Result<InputEvent, String> {
         match event {
             InputEvent::Paste { text } => {
@@ -0,0 +1,273 @@
+//! Usage Metering
+//!
+//! Per-tenant accounting of isolation usage for billing: session-minutes,
+//! streamed GB and peak concurrent sessions. Session metrics are sampled
+//! periodically into per-tenant accumulators and flushed to a
+//! [`UsageSink`] (the billing `MeteringEngine`) as `UsageEvent`s.
+//!
+//! Delivery goes through a [`UsageOutbox`], which retries failed flushes
+//! from a bounded backlog. Each event carries an idempotency key, so a
+//! batch that reached billing before the error was reported is not
+//! counted twice.
+
+use crate::IsolationSession;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use sase_billing::{UsageEvent, UsageMetric, UsageOutbox};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+pub use sase_billing::outbox::{FlushReport, UsageSink};
+
+const BYTES_PER_GB: f64 = 1_073_741_824.0;
+
+/// Metering configuration
+#[derive(Debug, Clone)]
+pub struct MeteringConfig {
+    /// Interval between flushes
+    pub flush_interval: Duration,
+    /// Events kept while billing is unavailable; oldest dropped beyond this
+    pub max_backlog: usize,
+    /// Reported as the `pop` dimension and part of idempotency keys
+    pub pop_location: String,
+}
+
+impl Default for MeteringConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: Duration::from_secs(60),
+            max_backlog: 100_000,
+            pop_location: "unknown".to_string(),
+        }
+    }
+}
+
+/// Usage accrued for a tenant since the last flush
+#[derive(Debug, Clone, Default)]
+struct TenantUsage {
+    session_minutes: f64,
+    streamed_bytes: u64,
+    active_sessions: u64,
+    /// Highest concurrency since the last flush
+    peak_sessions: u64,
+}
+
+/// What has already been accounted for a live session
+#[derive(Debug, Clone)]
+struct SessionCursor {
+    tenant_id: Uuid,
+    accounted_at: DateTime<Utc>,
+    bytes_streamed: u64,
+}
+
+/// Per-tenant RBI usage meter
+pub struct UsageMeter {
+    config: MeteringConfig,
+    sessions: Mutex<HashMap<String, SessionCursor>>,
+    tenants: Mutex<HashMap<Uuid, TenantUsage>>,
+    outbox: UsageOutbox,
+    sequence: AtomicU64,
+}
+
+impl UsageMeter {
+    pub fn new(config: MeteringConfig) -> Self {
+        Self {
+            outbox: UsageOutbox::new("RBI", config.max_backlog),
+            config,
+            sessions: Mutex::new(HashMap::new()),
+            tenants: Mutex::new(HashMap::new()),
+            sequence: AtomicU64::new(0),
+        }
+    }
+    
+    pub fn config(&self) -> &MeteringConfig {
+        &self.config
+    }
+    
+    /// Start accounting a session. Sessions without a billing tenant id
+    /// (a UUID) are not metered.
+    pub fn session_started(&self, session: &IsolationSession) {
+        let Some(tenant_id) = billing_tenant(session) else {
+            tracing::debug!("Session {} has no billing tenant; not metered", session.id);
+            return;
+        };
+        
+        self.sessions.lock().insert(session.id.clone(), SessionCursor {
+            tenant_id,
+            accounted_at: session.created_at,
+            bytes_streamed: session.metrics.bytes_streamed,
+        });
+        
+        let mut tenants = self.tenants.lock();
+        let usage = tenants.entry(tenant_id).or_default();
+        usage.active_sessions += 1;
+        usage.peak_sessions = usage.peak_sessions.max(usage.active_sessions);
+    }
+    
+    /// Accrue time and stream volume since the session was last sampled
+    pub fn observe(&self, session: &IsolationSession, now: DateTime<Utc>) {
+        let mut sessions = self.sessions.lock();
+        let Some(cursor) = sessions.get_mut(&session.id) else {
+            return;
+        };
+        
+        let seconds = (now - cursor.accounted_at).num_milliseconds().max(0) as f64 / 1000.0;
+        let bytes = session.metrics.bytes_streamed.saturating_sub(cursor.bytes_streamed);
+        cursor.accounted_at = cursor.accounted_at.max(now);
+        cursor.bytes_streamed = cursor.bytes_streamed.max(session.metrics.bytes_streamed);
+        
+        let mut tenants = self.tenants.lock();
+        let usage = tenants.entry(cursor.tenant_id).or_default();
+        usage.session_minutes += seconds / 60.0;
+        usage.streamed_bytes += bytes;
+    }
+    
+    /// Account the remainder of a session and stop tracking it
+    pub fn session_ended(&self, session: &IsolationSession, now: DateTime<Utc>) {
+        self.observe(session, now);
+        let Some(cursor) = self.sessions.lock().remove(&session.id) else {
+            return;
+        };
+        if let Some(usage) = self.tenants.lock().get_mut(&cursor.tenant_id) {
+            usage.active_sessions = usage.active_sessions.saturating_sub(1);
+        }
+    }
+    
+    /// Events waiting for delivery
+    pub fn backlog_len(&self) -> usize {
+        self.outbox.len()
+    }
+    
+    /// Convert accrued usage to events and deliver them with any backlog
+    pub async fn flush(&self, sink: &dyn UsageSink, now: DateTime<Utc>) -> FlushReport {
+        self.outbox.flush(sink, || self.drain(now)).await
+    }
+    
+    /// Take accrued usage as events; peak resets to current concurrency
+    fn drain(&self, now: DateTime<Utc>) -> Vec<UsageEvent> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let mut events = Vec::new();
+        let mut tenants = self.tenants.lock();
+        
+        for (tenant_id, usage) in tenants.iter_mut() {
+            let mut push = |metric: UsageMetric, name: &str, value: f64| {
+                events.push(UsageEvent {
+                    tenant_id: *tenant_id,
+                    timestamp: now,
+                    metric,
+                    value,
+                    dimensions: HashMap::from([
+                        ("service".to_string(), "rbi".to_string()),
+                        ("pop".to_string(), self.config.pop_location.clone()),
+                    ]),
+                    idempotency_key: Some(format!(
+                        "rbi:{}:{}:{}:{}:{}",
+                        self.config.pop_location, tenant_id, name, now.timestamp(), sequence,
+                    )),
+                });
+            };
+            
+            if usage.session_minutes > 0.0 {
+                push(UsageMetric::RbiSessionMinutes, "minutes", usage.session_minutes);
+            }
+            if usage.streamed_bytes > 0 {
+                push(UsageMetric::RbiStreamedGB, "gb", usage.streamed_bytes as f64 / BYTES_PER_GB);
+            }
+            if usage.peak_sessions > 0 {
+                push(UsageMetric::RbiPeakSessions, "peak", usage.peak_sessions as f64);
+            }
+            
+            usage.session_minutes = 0.0;
+            usage.streamed_bytes = 0;
+            usage.peak_sessions = usage.active_sessions;
+        }
+        
+        tenants.retain(|_, usage| usage.active_sessions > 0);
+        events
+    }
+}
+
+impl Default for UsageMeter {
+    fn default() -> Self {
+        Self::new(MeteringConfig::default())
+    }
+}
+
+/// Billing tenant of a session: its tenant id, falling back to the user
+fn billing_tenant(session: &IsolationSession) -> Option<Uuid> {
+    let tenant = session.config.tenant_id.as_deref().unwrap_or(&session.user_id);
+    Uuid::parse_str(tenant).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IsolationMode, SessionConfig, SessionMetrics, SessionStatus};
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use sase_billing::MeteringEngine;
+    
+    struct FlakySink {
+        engine: MeteringEngine,
+        up: std::sync::atomic::AtomicBool,
+    }
+    
+    #[async_trait]
+    impl UsageSink for FlakySink {
+        async fn submit(&self, events: Vec<UsageEvent>) -> Result<(), String> {
+            if !self.up.load(Ordering::Relaxed) {
+                return Err("connection refused".into());
+            }
+            self.engine.submit(events).await
+        }
+    }
+    
+    fn session(id: &str, tenant: Uuid, created_at: DateTime<Utc>) -> IsolationSession {
+        IsolationSession {
+            id: id.to_string(),
+            user_id: "alice".to_string(),
+            container_id: String::new(),
+            pop_location: "fra1".to_string(),
+            mode: IsolationMode::PixelPush,
+            status: SessionStatus::Active,
+            created_at,
+            last_activity: created_at,
+            config: SessionConfig { tenant_id: Some(tenant.to_string()), ..Default::default() },
+            metrics: SessionMetrics::default(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_metering_survives_billing_outage() {
+        let tenant = Uuid::new_v4();
+        let start = Utc.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap();
+        let meter = UsageMeter::default();
+        let sink = FlakySink { engine: MeteringEngine::new(), up: false.into() };
+        
+        let mut a = session("a", tenant, start);
+        let b = session("b", tenant, start);
+        meter.session_started(&a);
+        meter.session_started(&b);
+        
+        a.metrics.bytes_streamed = BYTES_PER_GB as u64;
+        meter.observe(&a, start + chrono::Duration::minutes(10));
+        meter.session_ended(&b, start + chrono::Duration::minutes(5));
+        
+        let failed = meter.flush(&sink, start + chrono::Duration::minutes(10)).await;
+        assert!(failed.error.is_some());
+        assert_eq!(failed.backlog, 3);
+        
+        sink.up.store(true, Ordering::Relaxed);
+        // Backlog plus the still-open session's concurrency
+        let sent = meter.flush(&sink, start + chrono::Duration::minutes(11)).await;
+        assert_eq!(sent.sent, 4);
+        assert_eq!(meter.backlog_len(), 0);
+        
+        let usage = sink.engine.get_monthly_usage(tenant, start.date_naive());
+        assert!((usage.total_rbi_session_minutes - 15.0).abs() < 0.01);
+        assert!((usage.total_rbi_streamed_gb - 1.0).abs() < 1e-9);
+        assert_eq!(usage.peak_rbi_sessions, 2);
+    }
+}
@@ -155,7 +155,7 @@ pub struct DlpPattern {
     pub sensitivity: Sensitivity,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Sensitivity {
     Low,
     Medium,
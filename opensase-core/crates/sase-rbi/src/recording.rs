@@ -0,0 +1,795 @@
+//! Session Recording
+//!
+//! Captures what happened in an isolated session for compliance review:
+//! video keyframes, DOM snapshots/updates, navigation and input events, each
+//! stamped with its offset from the start of the session.
+//!
+//! Events are buffered per session and written as segments encrypted with
+//! AES-256-GCM under a per-tenant key. The manifest (timing, segment list,
+//! review log) stays in the clear so retention sweeps don't need keys.
+//!
+//! ```text
+//! <storage>/<tenant>/<session>/manifest.json
+//! <storage>/<tenant>/<session>/<seq>.seg   "OSBR" | ver | key version | nonce | ciphertext
+//! ```
+
+use crate::{DomElement, DomUpdate, InputEvent, VideoCodec};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const SEGMENT_MAGIC: &[u8; 4] = b"OSBR";
+const SEGMENT_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Recording configuration
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+    /// Root directory for recordings
+    pub storage_path: PathBuf,
+    /// Flush a segment after this many events
+    pub segment_max_events: usize,
+    /// Flush a segment after this many buffered bytes
+    pub segment_max_bytes: usize,
+    /// Minimum spacing between recorded keyframes
+    pub keyframe_interval: Duration,
+    /// Mask typed characters and pasted text
+    pub redact_keystrokes: bool,
+    /// Retention for tenants without a policy
+    pub default_retention: RetentionPolicy,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            storage_path: PathBuf::from("/var/lib/osbi/recordings"),
+            segment_max_events: 2_000,
+            segment_max_bytes: 8 * 1024 * 1024,
+            keyframe_interval: Duration::from_secs(5),
+            redact_keystrokes: true,
+            default_retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+/// How long a tenant's recordings are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Days after the session ends
+    pub retain_days: u32,
+    /// Keep everything regardless of age
+    pub legal_hold: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            retain_days: 90,
+            legal_hold: false,
+        }
+    }
+}
+
+// =============================================================================
+// Keys
+// =============================================================================
+
+/// Per-tenant recording keys derived from a master key with HKDF-SHA256.
+///
+/// Rotation bumps the tenant's key version; earlier versions stay derivable
+/// so existing segments remain readable.
+pub struct TenantKeyring {
+    master: [u8; 32],
+    versions: dashmap::DashMap<String, u32>,
+}
+
+impl TenantKeyring {
+    pub fn new(master: [u8; 32]) -> Self {
+        Self {
+            master,
+            versions: dashmap::DashMap::new(),
+        }
+    }
+    
+    /// Keyring with a random master key (recordings unreadable after restart)
+    pub fn ephemeral() -> Self {
+        Self::new(Aes256Gcm::generate_key(OsRng).into())
+    }
+    
+    /// Current key version for a tenant
+    pub fn current_version(&self, tenant_id: &str) -> u32 {
+        self.versions.get(tenant_id).map(|v| *v).unwrap_or(1)
+    }
+    
+    /// Start encrypting new segments under a fresh key
+    pub fn rotate(&self, tenant_id: &str) -> u32 {
+        let mut version = self.versions.entry(tenant_id.to_string()).or_insert(1);
+        *version += 1;
+        *version
+    }
+    
+    fn key(&self, tenant_id: &str, version: u32) -> Key<Aes256Gcm> {
+        let hkdf = hkdf::Hkdf::<Sha256>::new(Some(b"osbi-recording"), &self.master);
+        let info = format!("{}/v{}", tenant_id, version);
+        let mut key = [0u8; 32];
+        // 32 bytes is always a valid HKDF-SHA256 output length
+        let _ = hkdf.expand(info.as_bytes(), &mut key);
+        key.into()
+    }
+}
+
+// =============================================================================
+// Events
+// =============================================================================
+
+/// Captured session event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordingEvent {
+    /// Encoded video keyframe
+    Keyframe {
+        codec: VideoCodec,
+        width: u32,
+        height: u32,
+        #[serde(with = "b64")]
+        data: Vec<u8>,
+    },
+    /// Full DOM of the page
+    DomSnapshot { url: String, elements: Vec<DomElement> },
+    /// Incremental DOM change
+    DomUpdate(DomUpdate),
+    /// User input (keystrokes redacted per config)
+    Input(InputEvent),
+    /// Top-level navigation
+    Navigation { url: String },
+    /// Download outcome
+    Download { download_id: String, filename: String, verdict: String },
+    /// Free-form marker (policy block, DLP hit, ...)
+    Marker { label: String },
+}
+
+impl RecordingEvent {
+    fn kind(&self) -> TimelineKind {
+        match self {
+            Self::Keyframe { .. } => TimelineKind::Keyframe,
+            Self::DomSnapshot { .. } => TimelineKind::DomSnapshot,
+            Self::DomUpdate(_) => TimelineKind::DomUpdate,
+            Self::Input(_) => TimelineKind::Input,
+            Self::Navigation { .. } => TimelineKind::Navigation,
+            Self::Download { .. } => TimelineKind::Download,
+            Self::Marker { .. } => TimelineKind::Marker,
+        }
+    }
+    
+    fn summary(&self) -> String {
+        match self {
+            Self::Keyframe { width, height, data, .. } => {
+                format!("Keyframe {}x{} ({} bytes)", width, height, data.len())
+            }
+            Self::DomSnapshot { url, elements } => format!("DOM snapshot of {} ({} elements)", url, elements.len()),
+            Self::DomUpdate(update) => match update {
+                DomUpdate::Add { parent_id, .. } => format!("Element added under {}", parent_id),
+                DomUpdate::Remove { element_id } => format!("Element {} removed", element_id),
+                DomUpdate::Modify { element_id, .. } => format!("Element {} modified", element_id),
+                DomUpdate::Scroll { x, y } => format!("Scrolled to {:.0},{:.0}", x, y),
+                DomUpdate::Navigate { url } => format!("Navigated to {}", url),
+            },
+            Self::Input(input) => match input {
+                InputEvent::Click { x, y, .. } => format!("Click at {:.0},{:.0}", x, y),
+                InputEvent::DoubleClick { x, y } => format!("Double-click at {:.0},{:.0}", x, y),
+                InputEvent::KeyDown { key, .. } | InputEvent::KeyPress { key, .. } => format!("Key {}", key),
+                InputEvent::Paste { text } => format!("Paste: {}", text),
+                InputEvent::Copy => "Copy".to_string(),
+                InputEvent::Cut => "Cut".to_string(),
+                InputEvent::Scroll { delta_y, .. } => format!("Scroll {:.0}", delta_y),
+                other => format!("{:?}", other).split([' ', '{']).next().unwrap_or_default().to_string(),
+            },
+            Self::Navigation { url } => format!("Navigated to {}", url),
+            Self::Download { filename, verdict, .. } => format!("Download {}: {}", filename, verdict),
+            Self::Marker { label } => label.clone(),
+        }
+    }
+    
+    fn is_replay_base(&self) -> bool {
+        matches!(self, Self::Keyframe { .. } | Self::DomSnapshot { .. })
+    }
+}
+
+/// Event with its position in the session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub seq: u64,
+    /// Milliseconds since recording started
+    pub offset_ms: u64,
+    pub at: DateTime<Utc>,
+    pub event: RecordingEvent,
+}
+
+// =============================================================================
+// Manifest & Timeline
+// =============================================================================
+
+/// Recording metadata (stored unencrypted)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingManifest {
+    pub session_id: String,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_ms: u64,
+    pub event_count: u64,
+    pub segments: Vec<SegmentInfo>,
+    /// Compliance reviewers who opened the recording
+    pub reviews: Vec<ReviewAccess>,
+}
+
+/// Encrypted segment metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    pub seq: u32,
+    pub key_version: u32,
+    pub first_offset_ms: u64,
+    pub last_offset_ms: u64,
+    pub events: usize,
+    pub bytes: usize,
+}
+
+/// Replay access log entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewAccess {
+    pub reviewer: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimelineKind {
+    Keyframe,
+    DomSnapshot,
+    DomUpdate,
+    Input,
+    Navigation,
+    Download,
+    Marker,
+}
+
+/// Timeline row for review UIs
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    pub seq: u64,
+    pub offset_ms: u64,
+    pub at: DateTime<Utc>,
+    pub kind: TimelineKind,
+    pub summary: String,
+}
+
+/// Reviewable timeline of a recorded session
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayTimeline {
+    pub session_id: String,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_ms: u64,
+    pub entries: Vec<TimelineEntry>,
+    /// Offsets a player can seek to without decoding from the start
+    pub seek_points: Vec<u64>,
+    pub counts: HashMap<String, u64>,
+}
+
+/// State needed to render the session at an offset: the latest keyframe or
+/// DOM snapshot, then every event up to the offset
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    pub offset_ms: u64,
+    pub base: Option<RecordedEvent>,
+    pub events: Vec<RecordedEvent>,
+}
+
+// =============================================================================
+// Recorder
+// =============================================================================
+
+struct ActiveRecording {
+    manifest: RecordingManifest,
+    started: Instant,
+    buffer: Vec<RecordedEvent>,
+    buffer_bytes: usize,
+    next_seq: u64,
+    last_keyframe: Option<Instant>,
+}
+
+/// Session recorder with encrypted segment storage and replay
+pub struct SessionRecorder {
+    config: RecordingConfig,
+    keyring: TenantKeyring,
+    active: dashmap::DashMap<String, ActiveRecording>,
+    /// Finished recordings: session ID -> manifest
+    finished: dashmap::DashMap<String, RecordingManifest>,
+    retention: dashmap::DashMap<String, RetentionPolicy>,
+}
+
+impl SessionRecorder {
+    pub fn new(config: RecordingConfig, keyring: TenantKeyring) -> Self {
+        Self {
+            config,
+            keyring,
+            active: dashmap::DashMap::new(),
+            finished: dashmap::DashMap::new(),
+            retention: dashmap::DashMap::new(),
+        }
+    }
+    
+    /// Key management (rotation)
+    pub fn keyring(&self) -> &TenantKeyring {
+        &self.keyring
+    }
+    
+    /// Set a tenant's retention policy
+    pub fn set_retention(&self, tenant_id: &str, policy: RetentionPolicy) {
+        self.retention.insert(tenant_id.to_string(), policy);
+    }
+    
+    /// Tenant's retention policy
+    pub fn retention(&self, tenant_id: &str) -> RetentionPolicy {
+        self.retention.get(tenant_id).map(|p| *p).unwrap_or(self.config.default_retention)
+    }
+    
+    /// Begin recording a session
+    pub fn start(&self, session_id: &str, tenant_id: &str, user_id: &str) {
+        let manifest = RecordingManifest {
+            session_id: session_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            user_id: user_id.to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            duration_ms: 0,
+            event_count: 0,
+            segments: Vec::new(),
+            reviews: Vec::new(),
+        };
+        
+        self.active.insert(session_id.to_string(), ActiveRecording {
+            manifest,
+            started: Instant::now(),
+            buffer: Vec::new(),
+            buffer_bytes: 0,
+            next_seq: 0,
+            last_keyframe: None,
+        });
+        tracing::info!("Recording session {} for tenant {}", session_id, tenant_id);
+    }
+    
+    /// Whether a session is being recorded
+    pub fn is_recording(&self, session_id: &str) -> bool {
+        self.active.contains_key(session_id)
+    }
+    
+    /// Capture an event. Keyframes closer together than `keyframe_interval`
+    /// are skipped; returns whether the event was recorded.
+    pub async fn record(&self, session_id: &str, event: RecordingEvent) -> Result<bool, RecordingError> {
+        let event = self.redact(event);
+        
+        let flush = {
+            let Some(mut rec) = self.active.get_mut(session_id) else {
+                return Ok(false);
+            };
+            
+            if matches!(event, RecordingEvent::Keyframe { .. }) {
+                if rec.last_keyframe.is_some_and(|t| t.elapsed() < self.config.keyframe_interval) {
+                    return Ok(false);
+                }
+                rec.last_keyframe = Some(Instant::now());
+            }
+            
+            let recorded = RecordedEvent {
+                seq: rec.next_seq,
+                offset_ms: rec.started.elapsed().as_millis() as u64,
+                at: Utc::now(),
+                event,
+            };
+            rec.next_seq += 1;
+            rec.buffer_bytes += estimated_size(&recorded.event);
+            rec.buffer.push(recorded);
+            
+            rec.buffer.len() >= self.config.segment_max_events
+                || rec.buffer_bytes >= self.config.segment_max_bytes
+        };
+        
+        if flush {
+            self.flush(session_id).await?;
+        }
+        Ok(true)
+    }
+    
+    /// Write buffered events as an encrypted segment
+    pub async fn flush(&self, session_id: &str) -> Result<(), RecordingError> {
+        let (events, manifest, seq) = {
+            let Some(mut rec) = self.active.get_mut(session_id) else {
+                return Ok(());
+            };
+            if rec.buffer.is_empty() {
+                return Ok(());
+            }
+            rec.buffer_bytes = 0;
+            let events = std::mem::take(&mut rec.buffer);
+            let seq = rec.manifest.segments.len() as u32;
+            (events, rec.manifest.clone(), seq)
+        };
+        
+        let info = self.write_segment(&manifest, seq, &events).await?;
+        
+        if let Some(mut rec) = self.active.get_mut(session_id) {
+            rec.manifest.event_count += events.len() as u64;
+            rec.manifest.duration_ms = info.last_offset_ms;
+            rec.manifest.segments.push(info);
+            let manifest = rec.manifest.clone();
+            drop(rec);
+            self.write_manifest(&manifest).await?;
+        }
+        Ok(())
+    }
+    
+    /// Finish a recording
+    pub async fn stop(&self, session_id: &str) -> Result<Option<RecordingManifest>, RecordingError> {
+        self.flush(session_id).await?;
+        
+        let Some((_, rec)) = self.active.remove(session_id) else {
+            return Ok(None);
+        };
+        let mut manifest = rec.manifest;
+        manifest.ended_at = Some(Utc::now());
+        manifest.duration_ms = rec.started.elapsed().as_millis() as u64;
+        
+        self.write_manifest(&manifest).await?;
+        self.finished.insert(session_id.to_string(), manifest.clone());
+        Ok(Some(manifest))
+    }
+    
+    /// Recordings for a tenant, newest first
+    pub fn list(&self, tenant_id: &str) -> Vec<RecordingManifest> {
+        let mut manifests: Vec<_> = self.finished.iter()
+            .filter(|m| m.tenant_id == tenant_id)
+            .map(|m| m.clone())
+            .collect();
+        manifests.sort_by_key(|m| std::cmp::Reverse(m.started_at));
+        manifests
+    }
+    
+    /// Build the review timeline; the access is logged on the manifest
+    pub async fn timeline(&self, tenant_id: &str, session_id: &str, reviewer: &str) -> Result<ReplayTimeline, RecordingError> {
+        let manifest = self.review(tenant_id, session_id, reviewer).await?;
+        let events = self.load_events(&manifest, 0, u64::MAX).await?;
+        
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        let mut seek_points = Vec::new();
+        let entries = events.iter()
+            .map(|e| {
+                *counts.entry(format!("{:?}", e.event.kind())).or_default() += 1;
+                if e.event.is_replay_base() {
+                    seek_points.push(e.offset_ms);
+                }
+                TimelineEntry {
+                    seq: e.seq,
+                    offset_ms: e.offset_ms,
+                    at: e.at,
+                    kind: e.event.kind(),
+                    summary: e.event.summary(),
+                }
+            })
+            .collect();
+        
+        Ok(ReplayTimeline {
+            session_id: manifest.session_id,
+            tenant_id: manifest.tenant_id,
+            user_id: manifest.user_id,
+            started_at: manifest.started_at,
+            ended_at: manifest.ended_at,
+            duration_ms: manifest.duration_ms,
+            entries,
+            seek_points,
+            counts,
+        })
+    }
+    
+    /// Events between two offsets (inclusive)
+    pub async fn events(&self, tenant_id: &str, session_id: &str, from_ms: u64, to_ms: u64) -> Result<Vec<RecordedEvent>, RecordingError> {
+        let manifest = self.manifest(tenant_id, session_id)?;
+        self.load_events(&manifest, from_ms, to_ms).await
+    }
+    
+    /// Seek: latest keyframe/DOM snapshot at or before `offset_ms` plus the
+    /// events needed to bring it up to that offset
+    pub async fn frame_at(&self, tenant_id: &str, session_id: &str, offset_ms: u64) -> Result<ReplayFrame, RecordingError> {
+        let manifest = self.manifest(tenant_id, session_id)?;
+        let events = self.load_events(&manifest, 0, offset_ms).await?;
+        
+        let base_index = events.iter().rposition(|e| e.event.is_replay_base());
+        let (base, events) = match base_index {
+            Some(i) => (Some(events[i].clone()), events[i + 1..].to_vec()),
+            None => (None, events),
+        };
+        
+        Ok(ReplayFrame { offset_ms, base, events })
+    }
+    
+    /// Delete finished recordings past their tenant's retention; returns
+    /// the session IDs removed
+    pub async fn enforce_retention(&self, now: DateTime<Utc>) -> Vec<String> {
+        let expired: Vec<RecordingManifest> = self.finished.iter()
+            .filter(|m| {
+                let policy = self.retention(&m.tenant_id);
+                let ended = m.ended_at.unwrap_or(m.started_at);
+                !policy.legal_hold && ended + chrono::Duration::days(policy.retain_days as i64) <= now
+            })
+            .map(|m| m.clone())
+            .collect();
+        
+        let mut removed = Vec::new();
+        for manifest in expired {
+            let dir = self.session_dir(&manifest.tenant_id, &manifest.session_id);
+            match tokio::fs::remove_dir_all(&dir).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    tracing::warn!("Failed to delete recording {}: {}", manifest.session_id, e);
+                    continue;
+                }
+            }
+            self.finished.remove(&manifest.session_id);
+            removed.push(manifest.session_id);
+        }
+        
+        if !removed.is_empty() {
+            tracing::info!("Retention removed {} recordings", removed.len());
+        }
+        removed
+    }
+    
+    fn manifest(&self, tenant_id: &str, session_id: &str) -> Result<RecordingManifest, RecordingError> {
+        self.finished.get(session_id)
+            .filter(|m| m.tenant_id == tenant_id)
+            .map(|m| m.clone())
+            .ok_or(RecordingError::NotFound)
+    }
+    
+    async fn review(&self, tenant_id: &str, session_id: &str, reviewer: &str) -> Result<RecordingManifest, RecordingError> {
+        let manifest = {
+            let mut entry = self.finished.get_mut(session_id)
+                .filter(|m| m.tenant_id == tenant_id)
+                .ok_or(RecordingError::NotFound)?;
+            entry.reviews.push(ReviewAccess {
+                reviewer: reviewer.to_string(),
+                at: Utc::now(),
+            });
+            entry.clone()
+        };
+        self.write_manifest(&manifest).await?;
+        Ok(manifest)
+    }
+    
+    async fn load_events(&self, manifest: &RecordingManifest, from_ms: u64, to_ms: u64) -> Result<Vec<RecordedEvent>, RecordingError> {
+        let mut events = Vec::new();
+        for segment in &manifest.segments {
+            if segment.last_offset_ms < from_ms || segment.first_offset_ms > to_ms {
+                continue;
+            }
+            let decoded = self.read_segment(manifest, segment.seq).await?;
+            events.extend(decoded.into_iter().filter(|e| e.offset_ms >= from_ms && e.offset_ms <= to_ms));
+        }
+        Ok(events)
+    }
+    
+    async fn write_segment(&self, manifest: &RecordingManifest, seq: u32, events: &[RecordedEvent]) -> Result<SegmentInfo, RecordingError> {
+        let plaintext = serde_json::to_vec(events)
+            .map_err(|e| RecordingError::Encoding(e.to_string()))?;
+        
+        let version = self.keyring.current_version(&manifest.tenant_id);
+        let cipher = Aes256Gcm::new(&self.keyring.key(&manifest.tenant_id, version));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = segment_aad(&manifest.tenant_id, &manifest.session_id, seq);
+        let ciphertext = cipher.encrypt(&nonce, Payload { msg: &plaintext, aad: aad.as_bytes() })
+            .map_err(|_| RecordingError::Crypto("encryption failed".to_string()))?;
+        
+        let mut out = Vec::with_capacity(4 + 1 + 4 + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(SEGMENT_MAGIC);
+        out.push(SEGMENT_VERSION);
+        out.extend_from_slice(&version.to_be_bytes());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        
+        let dir = self.session_dir(&manifest.tenant_id, &manifest.session_id);
+        tokio::fs::create_dir_all(&dir).await
+            .map_err(|e| RecordingError::Storage(e.to_string()))?;
+        tokio::fs::write(dir.join(format!("{:06}.seg", seq)), &out).await
+            .map_err(|e| RecordingError::Storage(e.to_string()))?;
+        
+        Ok(SegmentInfo {
+            seq,
+            key_version: version,
+            first_offset_ms: events.first().map(|e| e.offset_ms).unwrap_or(0),
+            last_offset_ms: events.last().map(|e| e.offset_ms).unwrap_or(0),
+            events: events.len(),
+            bytes: out.len(),
+        })
+    }
+    
+    async fn read_segment(&self, manifest: &RecordingManifest, seq: u32) -> Result<Vec<RecordedEvent>, RecordingError> {
+        let path = self.session_dir(&manifest.tenant_id, &manifest.session_id).join(format!("{:06}.seg", seq));
+        let data = tokio::fs::read(&path).await
+            .map_err(|e| RecordingError::Storage(e.to_string()))?;
+        
+        let header = 4 + 1 + 4 + NONCE_LEN;
+        if data.len() < header || &data[..4] != SEGMENT_MAGIC || data[4] != SEGMENT_VERSION {
+            return Err(RecordingError::Encoding(format!("invalid segment {}", seq)));
+        }
+        let version = u32::from_be_bytes([data[5], data[6], data[7], data[8]]);
+        let nonce = Nonce::from_slice(&data[9..header]);
+        
+        let cipher = Aes256Gcm::new(&self.keyring.key(&manifest.tenant_id, version));
+        let aad = segment_aad(&manifest.tenant_id, &manifest.session_id, seq);
+        let plaintext = cipher.decrypt(nonce, Payload { msg: &data[header..], aad: aad.as_bytes() })
+            .map_err(|_| RecordingError::Crypto(format!("segment {} failed authentication", seq)))?;
+        
+        serde_json::from_slice(&plaintext).map_err(|e| RecordingError::Encoding(e.to_string()))
+    }
+    
+    async fn write_manifest(&self, manifest: &RecordingManifest) -> Result<(), RecordingError> {
+        let dir = self.session_dir(&manifest.tenant_id, &manifest.session_id);
+        let json = serde_json::to_vec_pretty(manifest)
+            .map_err(|e| RecordingError::Encoding(e.to_string()))?;
+        tokio::fs::create_dir_all(&dir).await
+            .map_err(|e| RecordingError::Storage(e.to_string()))?;
+        tokio::fs::write(dir.join("manifest.json"), json).await
+            .map_err(|e| RecordingError::Storage(e.to_string()))
+    }
+    
+    fn session_dir(&self, tenant_id: &str, session_id: &str) -> PathBuf {
+        self.config.storage_path.join(path_safe(tenant_id)).join(path_safe(session_id))
+    }
+    
+    fn redact(&self, event: RecordingEvent) -> RecordingEvent {
+        if !self.config.redact_keystrokes {
+            return event;
+        }
+        let mask = |key: String| if key.chars().count() == 1 { "•".to_string() } else { key };
+        
+        match event {
+            RecordingEvent::Input(InputEvent::KeyDown { key, modifiers, .. }) => {
+                RecordingEvent::Input(InputEvent::KeyDown { key: mask(key), code: String::new(), modifiers })
+            }
+            RecordingEvent::Input(InputEvent::KeyUp { key, modifiers, .. }) => {
+                RecordingEvent::Input(InputEvent::KeyUp { key: mask(key), code: String::new(), modifiers })
+            }
+            RecordingEvent::Input(InputEvent::KeyPress { key, modifiers, .. }) => {
+                RecordingEvent::Input(InputEvent::KeyPress { key: mask(key), code: String::new(), modifiers })
+            }
+            RecordingEvent::Input(InputEvent::Paste { text }) => {
+                RecordingEvent::Input(InputEvent::Paste { text: format!("[{} chars redacted]", text.chars().count()) })
+            }
+            other => other,
+        }
+    }
+}
+
+fn segment_aad(tenant_id: &str, session_id: &str, seq: u32) -> String {
+    format!("{}/{}/{}", tenant_id, session_id, seq)
+}
+
+fn path_safe(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn estimated_size(event: &RecordingEvent) -> usize {
+    match event {
+        RecordingEvent::Keyframe { data, .. } => data.len() * 4 / 3 + 64,
+        RecordingEvent::DomSnapshot { elements, .. } => elements.len() * 256,
+        _ => 128,
+    }
+}
+
+/// Base64 for binary payloads in JSON segments
+mod b64 {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(data: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data))
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(d)?;
+        base64::engine::general_purpose::STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RecordingError {
+    NotFound,
+    Storage(String),
+    Encoding(String),
+    Crypto(String),
+}
+
+impl std::fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Recording not found"),
+            Self::Storage(e) => write!(f, "Recording storage error: {}", e),
+            Self::Encoding(e) => write!(f, "Recording encoding error: {}", e),
+            Self::Crypto(e) => write!(f, "Recording crypto error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Modifiers;
+    
+    fn recorder() -> SessionRecorder {
+        SessionRecorder::new(
+            RecordingConfig {
+                storage_path: std::env::temp_dir().join(format!("osbi-rec-{}", uuid::Uuid::new_v4())),
+                segment_max_events: 2,
+                ..Default::default()
+            },
+            TenantKeyring::new([7u8; 32]),
+        )
+    }
+    
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let recorder = recorder();
+        recorder.start("s1", "acme", "alice");
+        
+        let keyframe = RecordingEvent::Keyframe { codec: VideoCodec::H264, width: 2, height: 2, data: vec![1, 2, 3] };
+        assert!(recorder.record("s1", keyframe.clone()).await.unwrap());
+        // Within keyframe_interval: skipped
+        assert!(!recorder.record("s1", keyframe).await.unwrap());
+        
+        let key = InputEvent::KeyPress { key: "p".into(), code: "KeyP".into(), modifiers: Modifiers::default() };
+        recorder.record("s1", RecordingEvent::Input(key)).await.unwrap();
+        recorder.record("s1", RecordingEvent::Navigation { url: "https://example.com".into() }).await.unwrap();
+        
+        let manifest = recorder.stop("s1").await.unwrap().unwrap();
+        assert_eq!(manifest.event_count, 3);
+        assert_eq!(manifest.segments.len(), 2);
+        
+        let timeline = recorder.timeline("acme", "s1", "auditor").await.unwrap();
+        assert_eq!(timeline.entries.len(), 3);
+        assert_eq!(timeline.seek_points.len(), 1);
+        assert_eq!(timeline.entries[1].summary, "Key •");
+        assert!(recorder.timeline("other", "s1", "auditor").await.is_err());
+        
+        let frame = recorder.frame_at("acme", "s1", u64::MAX).await.unwrap();
+        assert!(frame.base.is_some());
+        assert_eq!(frame.events.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_segments_bound_to_tenant_key() {
+        let recorder = recorder();
+        recorder.start("s2", "acme", "bob");
+        recorder.record("s2", RecordingEvent::Marker { label: "a".into() }).await.unwrap();
+        recorder.record("s2", RecordingEvent::Marker { label: "b".into() }).await.unwrap();
+        recorder.stop("s2").await.unwrap();
+        
+        let other = SessionRecorder::new(recorder.config.clone(), TenantKeyring::new([9u8; 32]));
+        let manifest = recorder.manifest("acme", "s2").unwrap();
+        assert!(matches!(other.read_segment(&manifest, 0).await, Err(RecordingError::Crypto(_))));
+        
+        recorder.set_retention("acme", RetentionPolicy { retain_days: 0, legal_hold: true });
+        assert!(recorder.enforce_retention(Utc::now()).await.is_empty());
+        recorder.set_retention("acme", RetentionPolicy { retain_days: 0, legal_hold: false });
+        assert_eq!(recorder.enforce_retention(Utc::now()).await, vec!["s2".to_string()]);
+    }
+}
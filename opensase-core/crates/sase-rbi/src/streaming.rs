@@ -2,7 +2,8 @@
 //!
 //! Pixel-push and DOM reconstruction streaming.
 
-use crate::{IsolationMode, StreamConfig, StreamQuality, VideoCodec, DomElement, DomUpdate, Viewport, BoundingBox};
+use crate::{IsolationMode, StreamConfig, StreamQuality, VideoCodec, DomElement, DomUpdate, Viewport, BoundingBox, SessionMetrics};
+use crate::transport::{BitrateController, BitrateDecision, TransportEvent, TransportKind};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc};
@@ -22,6 +23,11 @@ pub struct StreamState {
     pub config: StreamConfig,
     pub frame_tx: broadcast::Sender<StreamFrame>,
     pub stats: StreamStats,
+    /// Active transport, once connected
+    pub transport: Option<TransportKind>,
+    pub bitrate: BitrateController,
+    /// Set by PLI/FIR or dropped frames; cleared by the encoder loop
+    pub keyframe_requested: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -105,13 +111,17 @@ impl StreamManager {
         config: Option<StreamConfig>,
     ) -> broadcast::Receiver<StreamFrame> {
         let (tx, rx) = broadcast::channel(100);
+        let config = config.unwrap_or_else(|| self.default_config.clone());
         
         let state = StreamState {
             session_id: session_id.to_string(),
             mode,
-            config: config.unwrap_or_else(|| self.default_config.clone()),
+            bitrate: BitrateController::new(&config),
+            config,
             frame_tx: tx,
             stats: StreamStats::default(),
+            transport: None,
+            keyframe_requested: false,
         };
         
         self.streams.insert(session_id.to_string(), state);
@@ -155,6 +165,46 @@ impl StreamManager {
     pub fn get_stats(&self, session_id: &str) -> Option<StreamStats> {
         self.streams.get(session_id).map(|s| s.stats.clone())
     }
+    
+    /// Apply transport feedback
+    pub fn on_transport_event(&self, session_id: &str, event: &TransportEvent) {
+        if let Some(mut stream) = self.streams.get_mut(session_id) {
+            match event {
+                TransportEvent::Connected(kind) => stream.transport = Some(*kind),
+                TransportEvent::Closed { kind, .. } if stream.transport == Some(*kind) => {
+                    stream.transport = None;
+                }
+                TransportEvent::KeyframeRequested => stream.keyframe_requested = true,
+                _ => stream.bitrate.on_event(event),
+            }
+        }
+    }
+    
+    /// Re-evaluate encoder bitrate from session metrics. Returns new encoder
+    /// settings when they should change.
+    pub fn adapt_bitrate(&self, session_id: &str, metrics: &SessionMetrics) -> Option<BitrateDecision> {
+        let mut stream = self.streams.get_mut(session_id)?;
+        if stream.mode == IsolationMode::DomReconstruction {
+            return None;
+        }
+        
+        let decision = stream.bitrate.update(metrics)?;
+        stream.stats.current_bitrate_kbps = decision.bitrate_kbps;
+        stream.stats.current_fps = decision.fps as f32;
+        Some(decision)
+    }
+    
+    /// Consume a pending keyframe request
+    pub fn take_keyframe_request(&self, session_id: &str) -> bool {
+        self.streams.get_mut(session_id)
+            .map(|mut s| std::mem::take(&mut s.keyframe_requested))
+            .unwrap_or(false)
+    }
+    
+    /// Transport currently carrying the stream
+    pub fn transport(&self, session_id: &str) -> Option<TransportKind> {
+        self.streams.get(session_id).and_then(|s| s.transport)
+    }
 }
 
 impl Default for StreamManager {
@@ -271,7 +321,7 @@ impl DomSerializer {
         let dangerous = ["onclick", "onerror", "onload", "onmouseover", "onfocus"];
         
         for attr in dangerous {
-            element.attributes.remove(*attr);
+            element.attributes.remove(attr);
         }
         
         // Remove script tags
@@ -1,9 +1,17 @@
 //! SWG Integration
 //!
 //! Secure Web Gateway integration for isolation decisions.
+//!
+//! [`decide`] routes a URL for a user to direct access, isolation (with the
+//! isolation mode to use) or block, combining URL category, threat
+//! intelligence, user group and device trust. The ZTNA gateway calls it for
+//! web resources before granting access.
 
 use crate::policy::{UrlPolicy, UrlDecision, UrlCategory};
+use crate::IsolationMode;
+use parking_lot::RwLock;
 use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
 
 /// SWG-RBI integration for isolation decisions
 pub struct SwgIntegration {
@@ -11,10 +19,161 @@ pub struct SwgIntegration {
     url_policy: UrlPolicy,
     /// Isolation rules
     isolation_rules: IsolationRules,
+    /// Group/category/device routing rules
+    routing: RoutingPolicy,
+    /// Reputation and categorization lookups
+    threat_intel: Option<Arc<dyn ThreatIntel>>,
     /// Statistics
     stats: SwgStats,
 }
 
+/// Where a web request goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessRoute {
+    /// Connect directly
+    Direct,
+    /// Render in an isolated browser
+    Isolate {
+        mode: IsolationMode,
+        reason: IsolationReason,
+    },
+    /// Deny
+    Block { reason: String },
+}
+
+impl AccessRoute {
+    pub fn is_isolated(&self) -> bool {
+        matches!(self, Self::Isolate { .. })
+    }
+}
+
+/// Who is browsing and from what device
+#[derive(Debug, Clone)]
+pub struct UserContext {
+    pub user_id: String,
+    pub groups: Vec<String>,
+    pub device_trust: DeviceTrust,
+    pub managed_device: bool,
+    pub source_ip: Option<IpAddr>,
+}
+
+impl UserContext {
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            groups: Vec::new(),
+            device_trust: DeviceTrust::Medium,
+            managed_device: false,
+            source_ip: None,
+        }
+    }
+    
+    pub fn with_groups(mut self, groups: Vec<String>) -> Self {
+        self.groups = groups;
+        self
+    }
+    
+    pub fn with_device(mut self, trust: DeviceTrust, managed: bool) -> Self {
+        self.device_trust = trust;
+        self.managed_device = managed;
+        self
+    }
+    
+    fn in_any_group(&self, groups: &[String]) -> bool {
+        groups.is_empty() || self.groups.iter().any(|g| groups.iter().any(|r| r.eq_ignore_ascii_case(g)))
+    }
+}
+
+/// Device trust as assessed by posture checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeviceTrust {
+    Untrusted,
+    Low,
+    Medium,
+    High,
+    Full,
+}
+
+/// Threat intelligence verdict for a domain
+#[derive(Debug, Clone, Default)]
+pub struct DomainIntel {
+    /// Category from the categorization feed
+    pub category: Option<UrlCategory>,
+    /// Reputation risk (0-100)
+    pub risk_score: u32,
+    /// Listed as malicious (malware, phishing, C2)
+    pub malicious: bool,
+    /// Feed that produced the verdict
+    pub source: String,
+}
+
+/// Threat intelligence lookup (reputation and categorization feeds)
+pub trait ThreatIntel: Send + Sync {
+    fn lookup(&self, domain: &str) -> Option<DomainIntel>;
+}
+
+/// Routing rule: first matching rule decides
+#[derive(Debug, Clone)]
+pub struct RoutingRule {
+    pub name: String,
+    /// User groups the rule applies to (empty = everyone)
+    pub groups: Vec<String>,
+    /// URL categories the rule applies to (empty = any)
+    pub categories: Vec<UrlCategory>,
+    /// Applies only when device trust is below this level
+    pub device_trust_below: Option<DeviceTrust>,
+    /// Applies only at or above this risk score
+    pub min_risk: Option<u32>,
+    pub action: RouteAction,
+}
+
+/// Routing rule action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteAction {
+    Direct,
+    Isolate(IsolationMode),
+    Block,
+}
+
+/// Smart isolation routing policy
+#[derive(Debug, Clone)]
+pub struct RoutingPolicy {
+    /// Ordered rules evaluated before the built-in isolation rules
+    pub rules: Vec<RoutingRule>,
+    /// Block domains with a threat intel malicious verdict
+    pub block_malicious: bool,
+    /// Risk at or above which requests are blocked outright
+    pub block_threshold: u32,
+    /// Isolate everything not bypassed from devices below this trust
+    pub isolate_below_trust: DeviceTrust,
+    /// Unmanaged devices are isolated for non-bypassed sites
+    pub isolate_unmanaged: bool,
+    /// Risk at or above which pixel push is used instead of DOM reconstruction
+    pub pixel_push_threshold: u32,
+}
+
+impl Default for RoutingPolicy {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            block_malicious: true,
+            block_threshold: 90,
+            isolate_below_trust: DeviceTrust::Medium,
+            isolate_unmanaged: false,
+            pixel_push_threshold: 70,
+        }
+    }
+}
+
+impl RoutingRule {
+    fn matches(&self, ctx: &UserContext, category: UrlCategory, risk: u32) -> bool {
+        ctx.in_any_group(&self.groups)
+            && (self.categories.is_empty() || self.categories.contains(&category))
+            && self.device_trust_below.is_none_or(|t| ctx.device_trust < t)
+            && self.min_risk.is_none_or(|r| risk >= r)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IsolationRules {
     /// Always isolate these categories
@@ -64,7 +223,7 @@ pub enum IsolationDecision {
     Block(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum IsolationReason {
     Category(UrlCategory),
     Domain,
@@ -73,6 +232,10 @@ pub enum IsolationReason {
     FirstVisit,
     UserPolicy,
     Manual,
+    /// Matched a routing rule
+    Rule(String),
+    /// Device below required trust or unmanaged
+    DeviceTrust,
 }
 
 #[derive(Debug, Default)]
@@ -99,66 +262,170 @@ impl SwgIntegration {
         Self {
             url_policy,
             isolation_rules,
+            routing: RoutingPolicy::default(),
+            threat_intel: None,
             stats: SwgStats::default(),
         }
     }
     
-    /// Decide whether to isolate a request
-    pub fn decide(&self, request: &HttpRequest) -> IsolationDecision {
+    /// Set group/category/device routing policy
+    pub fn with_routing(mut self, routing: RoutingPolicy) -> Self {
+        self.routing = routing;
+        self
+    }
+    
+    /// Use a threat intelligence feed for reputation and categories
+    pub fn with_threat_intel(mut self, intel: Arc<dyn ThreatIntel>) -> Self {
+        self.threat_intel = Some(intel);
+        self
+    }
+    
+    /// Route a URL for a user: direct, isolate (and how), or block
+    pub fn route(&self, url: &str, ctx: &UserContext) -> AccessRoute {
         use std::sync::atomic::Ordering;
         
         self.stats.requests_total.fetch_add(1, Ordering::Relaxed);
+        let route = self.evaluate(url, ctx, None);
+        let counter = match &route {
+            AccessRoute::Direct => &self.stats.requests_allowed,
+            AccessRoute::Isolate { .. } => &self.stats.requests_isolated,
+            AccessRoute::Block { .. } => &self.stats.requests_blocked,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        route
+    }
+    
+    fn evaluate(&self, url: &str, ctx: &UserContext, referer: Option<&str>) -> AccessRoute {
+        let domain = extract_domain(url);
         
-        let domain = extract_domain(&request.url);
-        
-        // Check bypass list (trusted domains)
-        if self.is_bypassed(&domain) {
-            self.stats.requests_allowed.fetch_add(1, Ordering::Relaxed);
-            return IsolationDecision::Allow;
-        }
-        
-        // Check URL policy first
-        match self.url_policy.check(&request.url) {
+        // Explicit policy blocks apply even to trusted domains
+        match self.url_policy.check(url) {
             UrlDecision::Block(reason) => {
-                self.stats.requests_blocked.fetch_add(1, Ordering::Relaxed);
-                return IsolationDecision::Block(format!("{:?}", reason));
+                return AccessRoute::Block { reason: format!("{:?}", reason) };
             }
             UrlDecision::Isolate => {
-                self.stats.requests_isolated.fetch_add(1, Ordering::Relaxed);
-                return IsolationDecision::Isolate(IsolationReason::UserPolicy);
+                return AccessRoute::Isolate {
+                    mode: IsolationMode::PixelPush,
+                    reason: IsolationReason::UserPolicy,
+                };
             }
             _ => {}
         }
         
-        // Check explicit isolation domains
-        if self.isolation_rules.isolate_domains.iter()
-            .any(|d| domain.ends_with(d)) {
-            self.stats.requests_isolated.fetch_add(1, Ordering::Relaxed);
-            return IsolationDecision::Isolate(IsolationReason::Domain);
+        // Trusted domains
+        if self.is_bypassed(&domain) {
+            return AccessRoute::Direct;
         }
         
-        // Check category-based isolation
-        let category = self.categorize(&domain);
-        if self.isolation_rules.isolate_categories.contains(&category) {
-            self.stats.requests_isolated.fetch_add(1, Ordering::Relaxed);
-            return IsolationDecision::Isolate(IsolationReason::Category(category));
+        // Threat intelligence
+        let intel = self.threat_intel.as_ref().and_then(|ti| ti.lookup(&domain));
+        if let Some(intel) = &intel {
+            if intel.malicious && self.routing.block_malicious {
+                return AccessRoute::Block {
+                    reason: format!("Malicious domain ({})", intel.source),
+                };
+            }
         }
         
-        // Check risk score
-        let risk_score = self.calculate_risk(&request);
-        if risk_score >= self.isolation_rules.risk_threshold {
-            self.stats.requests_isolated.fetch_add(1, Ordering::Relaxed);
-            return IsolationDecision::Isolate(IsolationReason::RiskScore(risk_score));
+        let category = intel.as_ref()
+            .and_then(|i| i.category)
+            .unwrap_or_else(|| self.categorize(&domain));
+        let risk = self.calculate_risk(url, referer)
+            .max(intel.as_ref().map(|i| i.risk_score).unwrap_or(0))
+            .min(100);
+        
+        if risk >= self.routing.block_threshold {
+            return AccessRoute::Block { reason: format!("Risk score {}", risk) };
+        }
+        
+        // Admin routing rules
+        if let Some(rule) = self.routing.rules.iter().find(|r| r.matches(ctx, category, risk)) {
+            return match rule.action {
+                RouteAction::Direct => AccessRoute::Direct,
+                RouteAction::Isolate(mode) => AccessRoute::Isolate {
+                    mode,
+                    reason: IsolationReason::Rule(rule.name.clone()),
+                },
+                RouteAction::Block => AccessRoute::Block {
+                    reason: format!("Blocked by rule '{}'", rule.name),
+                },
+            };
         }
         
-        // Uncategorized handling
+        let isolate = |reason| AccessRoute::Isolate {
+            mode: self.select_mode(category, risk, ctx),
+            reason,
+        };
+        
+        if self.isolation_rules.isolate_domains.iter().any(|d| domain.ends_with(d)) {
+            return isolate(IsolationReason::Domain);
+        }
+        if self.isolation_rules.isolate_categories.contains(&category) {
+            return isolate(IsolationReason::Category(category));
+        }
+        if risk >= self.isolation_rules.risk_threshold {
+            return isolate(IsolationReason::RiskScore(risk));
+        }
         if category == UrlCategory::Unknown && self.isolation_rules.isolate_uncategorized {
-            self.stats.requests_isolated.fetch_add(1, Ordering::Relaxed);
-            return IsolationDecision::Isolate(IsolationReason::Uncategorized);
+            return isolate(IsolationReason::Uncategorized);
+        }
+        
+        // Low-trust or unmanaged devices never browse directly
+        if ctx.device_trust < self.routing.isolate_below_trust
+            || (self.routing.isolate_unmanaged && !ctx.managed_device) {
+            return isolate(IsolationReason::DeviceTrust);
         }
         
-        self.stats.requests_allowed.fetch_add(1, Ordering::Relaxed);
-        IsolationDecision::Allow
+        AccessRoute::Direct
+    }
+    
+    /// Pixel push for risky content or untrusted devices; DOM reconstruction
+    /// otherwise, hybrid for media-heavy categories
+    fn select_mode(&self, category: UrlCategory, risk: u32, ctx: &UserContext) -> IsolationMode {
+        if risk >= self.routing.pixel_push_threshold
+            || ctx.device_trust <= DeviceTrust::Low
+            || matches!(category, UrlCategory::Malware | UrlCategory::Phishing | UrlCategory::Unknown) {
+            IsolationMode::PixelPush
+        } else if matches!(category, UrlCategory::Streaming | UrlCategory::SocialMedia | UrlCategory::Gaming) {
+            IsolationMode::Hybrid
+        } else {
+            IsolationMode::DomReconstruction
+        }
+    }
+    
+    /// Decide whether to isolate a request
+    pub fn decide(&self, request: &HttpRequest) -> IsolationDecision {
+        use std::sync::atomic::Ordering;
+        
+        self.stats.requests_total.fetch_add(1, Ordering::Relaxed);
+        
+        let ctx = UserContext {
+            source_ip: Some(request.source_ip),
+            ..UserContext::new(&request.user_id)
+        };
+        match self.evaluate(&request.url, &ctx, request.referer.as_deref()) {
+            AccessRoute::Direct => {
+                self.stats.requests_allowed.fetch_add(1, Ordering::Relaxed);
+                IsolationDecision::Allow
+            }
+            AccessRoute::Isolate { reason, .. } => {
+                self.stats.requests_isolated.fetch_add(1, Ordering::Relaxed);
+                IsolationDecision::Isolate(reason)
+            }
+            AccessRoute::Block { reason } => {
+                self.stats.requests_blocked.fetch_add(1, Ordering::Relaxed);
+                IsolationDecision::Block(reason)
+            }
+        }
+    }
+    
+    /// Category of a URL from threat intel, falling back to the built-in categorizer
+    pub fn categorize_url(&self, url: &str) -> UrlCategory {
+        let domain = extract_domain(url);
+        self.threat_intel.as_ref()
+            .and_then(|ti| ti.lookup(&domain))
+            .and_then(|i| i.category)
+            .unwrap_or_else(|| self.categorize(&domain))
     }
     
     /// Force isolation for a domain
@@ -181,18 +448,18 @@ impl SwgIntegration {
         UrlCategory::Unknown
     }
     
-    fn calculate_risk(&self, request: &HttpRequest) -> u32 {
+    fn calculate_risk(&self, url: &str, referer: Option<&str>) -> u32 {
         let mut score = 0u32;
         
         // New domain
-        let domain = extract_domain(&request.url);
+        let domain = extract_domain(url);
         if domain.len() > 30 {
             score += 10; // Long domains are suspicious
         }
         
         // Contains IP address
-        if request.url.chars().filter(|c| *c == '.').count() >= 3 &&
-           request.url.chars().all(|c| c.is_numeric() || c == '.' || c == '/' || c == ':') {
+        if url.chars().filter(|c| *c == '.').count() >= 3 &&
+           url.chars().all(|c| c.is_numeric() || c == '.' || c == '/' || c == ':') {
             score += 30;
         }
         
@@ -203,7 +470,7 @@ impl SwgIntegration {
         }
         
         // No referer on non-main page
-        if request.referer.is_none() && request.url.contains('?') {
+        if referer.is_none() && url.contains('?') {
             score += 10;
         }
         
@@ -231,6 +498,29 @@ pub struct SwgSnapshot {
     pub requests_blocked: u64,
 }
 
+static GLOBAL: OnceLock<RwLock<Arc<SwgIntegration>>> = OnceLock::new();
+
+fn global() -> &'static RwLock<Arc<SwgIntegration>> {
+    GLOBAL.get_or_init(|| {
+        RwLock::new(Arc::new(SwgIntegration::new(UrlPolicy::default(), IsolationRules::default())))
+    })
+}
+
+/// Install the process-wide SWG policy used by [`decide`]
+pub fn install(swg: SwgIntegration) {
+    *global().write() = Arc::new(swg);
+}
+
+/// Process-wide SWG policy
+pub fn current() -> Arc<SwgIntegration> {
+    global().read().clone()
+}
+
+/// Route a URL for a user with the installed SWG policy
+pub fn decide(url: &str, user_ctx: &UserContext) -> AccessRoute {
+    current().route(url, user_ctx)
+}
+
 fn extract_domain(url: &str) -> String {
     url.trim_start_matches("http://")
         .trim_start_matches("https://")
@@ -265,4 +555,72 @@ mod tests {
         
         assert!(matches!(swg.decide(&request), IsolationDecision::Allow));
     }
+    
+    struct StaticIntel;
+    
+    impl ThreatIntel for StaticIntel {
+        fn lookup(&self, domain: &str) -> Option<DomainIntel> {
+            match domain {
+                "evil.example" => Some(DomainIntel {
+                    malicious: true,
+                    risk_score: 100,
+                    source: "test-feed".to_string(),
+                    ..Default::default()
+                }),
+                "news.example" => Some(DomainIntel {
+                    category: Some(UrlCategory::News),
+                    risk_score: 10,
+                    source: "test-feed".to_string(),
+                    ..Default::default()
+                }),
+                _ => None,
+            }
+        }
+    }
+    
+    #[test]
+    fn test_route_by_intel_group_and_device() {
+        let routing = RoutingPolicy {
+            rules: vec![RoutingRule {
+                name: "contractors-isolated".to_string(),
+                groups: vec!["contractors".to_string()],
+                categories: Vec::new(),
+                device_trust_below: None,
+                min_risk: None,
+                action: RouteAction::Isolate(IsolationMode::PixelPush),
+            }],
+            ..Default::default()
+        };
+        let swg = SwgIntegration::new(UrlPolicy::default(), IsolationRules::default())
+            .with_routing(routing)
+            .with_threat_intel(Arc::new(StaticIntel));
+        
+        let employee = UserContext::new("alice")
+            .with_groups(vec!["employees".to_string()])
+            .with_device(DeviceTrust::High, true);
+        let contractor = UserContext::new("bob")
+            .with_groups(vec!["contractors".to_string()])
+            .with_device(DeviceTrust::High, true);
+        let untrusted = UserContext::new("carol").with_device(DeviceTrust::Low, false);
+        
+        assert!(matches!(swg.route("https://evil.example/login", &employee), AccessRoute::Block { .. }));
+        assert_eq!(swg.route("https://news.example/today", &employee), AccessRoute::Direct);
+        assert_eq!(
+            swg.route("https://news.example/today", &contractor),
+            AccessRoute::Isolate {
+                mode: IsolationMode::PixelPush,
+                reason: IsolationReason::Rule("contractors-isolated".to_string()),
+            }
+        );
+        assert_eq!(
+            swg.route("https://news.example/today", &untrusted),
+            AccessRoute::Isolate {
+                mode: IsolationMode::PixelPush,
+                reason: IsolationReason::DeviceTrust,
+            }
+        );
+        // Uncategorized sites are isolated; managed devices still get pixels
+        assert!(swg.route("https://unknown.example/", &employee).is_isolated());
+        assert_eq!(swg.get_stats().requests_total, 5);
+    }
 }
@@ -0,0 +1,444 @@
+//! Stream Transport
+//!
+//! Carries pixel-push frames to the client. WebRTC is preferred; the
+//! WebSocket fallback covers networks that block UDP or intercept DTLS.
+//! Both paths report feedback as [`TransportEvent`]s which drive the
+//! [`BitrateController`] and keyframe requests in the stream manager.
+
+use crate::streaming::{CursorType, StreamFrame};
+use crate::webrtc::{WebRtcError, WebRtcPeer};
+use crate::{SessionMetrics, StreamConfig, StreamQuality, VideoCodec};
+use futures_util::{SinkExt, StreamExt};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::UdpSocket;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Floor below which the picture is unusable
+const MIN_BITRATE_KBPS: u32 = 300;
+/// Fraction of measured bandwidth the stream may use
+const BANDWIDTH_HEADROOM: f64 = 0.85;
+/// Changes smaller than this are not worth reconfiguring the encoder for
+const MIN_CHANGE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    WebRtc,
+    WebSocket,
+}
+
+/// Feedback from a transport to the stream manager
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportEvent {
+    Connected(TransportKind),
+    /// Client decoder needs an IDR (PLI/FIR, or frames were dropped)
+    KeyframeRequested,
+    /// Fraction of packets lost since the last report
+    PacketLoss(f64),
+    /// Receiver-side bandwidth estimate
+    BandwidthEstimate(u32),
+    Closed { kind: TransportKind, reason: Option<String> },
+}
+
+// =============================================================================
+// Bitrate Adaptation
+// =============================================================================
+
+/// Encoder settings chosen by the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitrateDecision {
+    pub bitrate_kbps: u32,
+    pub fps: u32,
+    pub quality: StreamQuality,
+}
+
+/// Loss- and delay-based bitrate controller.
+///
+/// Backs off multiplicatively on heavy loss or rising latency, probes up
+/// slowly when the path is clean, and never exceeds the measured
+/// `SessionMetrics::bandwidth_kbps` (less headroom) or the stream's
+/// configured maximum.
+#[derive(Debug, Clone)]
+pub struct BitrateController {
+    target_kbps: f64,
+    max_kbps: u32,
+    max_fps: u32,
+    loss: f64,
+    estimate_kbps: Option<u32>,
+    min_latency_ms: Option<f64>,
+    applied: Option<BitrateDecision>,
+}
+
+impl BitrateController {
+    pub fn new(config: &StreamConfig) -> Self {
+        let max_kbps = config.max_bitrate_kbps.max(MIN_BITRATE_KBPS);
+        Self {
+            // Start conservatively and probe up
+            target_kbps: (max_kbps / 2).max(MIN_BITRATE_KBPS) as f64,
+            max_kbps,
+            max_fps: config.max_fps,
+            loss: 0.0,
+            estimate_kbps: None,
+            min_latency_ms: None,
+            applied: None,
+        }
+    }
+
+    /// Record transport feedback
+    pub fn on_event(&mut self, event: &TransportEvent) {
+        match event {
+            TransportEvent::PacketLoss(loss) => self.loss = loss.clamp(0.0, 1.0),
+            TransportEvent::BandwidthEstimate(kbps) => self.estimate_kbps = Some(*kbps),
+            _ => {}
+        }
+    }
+
+    /// Re-evaluate against session metrics. Returns a decision only when
+    /// the encoder should be reconfigured.
+    pub fn update(&mut self, metrics: &SessionMetrics) -> Option<BitrateDecision> {
+        let mut target = self.target_kbps;
+
+        // Queueing delay: latency well above the best seen means buffers are filling
+        let congested = metrics.latency_ms > 0.0 && self.min_latency_ms
+            .is_some_and(|base| metrics.latency_ms > base * 2.0 + 20.0);
+        if metrics.latency_ms > 0.0 {
+            self.min_latency_ms = Some(self.min_latency_ms.map_or(metrics.latency_ms, |m| m.min(metrics.latency_ms)));
+        }
+
+        if self.loss > 0.10 {
+            target *= 1.0 - 0.5 * self.loss;
+        } else if congested {
+            target *= 0.85;
+        } else if self.loss < 0.02 {
+            target *= 1.08;
+        }
+
+        let mut ceiling = self.max_kbps as f64;
+        if metrics.bandwidth_kbps > 0.0 {
+            ceiling = ceiling.min(metrics.bandwidth_kbps * BANDWIDTH_HEADROOM);
+        }
+        if let Some(estimate) = self.estimate_kbps {
+            ceiling = ceiling.min(estimate as f64);
+        }
+        self.target_kbps = target.min(ceiling).max(MIN_BITRATE_KBPS as f64);
+
+        let decision = self.decision();
+        let changed = match self.applied {
+            None => true,
+            Some(prev) => {
+                prev.fps != decision.fps
+                    || prev.quality != decision.quality
+                    || (decision.bitrate_kbps as f64 - prev.bitrate_kbps as f64).abs()
+                        > prev.bitrate_kbps as f64 * MIN_CHANGE
+            }
+        };
+
+        if changed {
+            self.applied = Some(decision);
+            Some(decision)
+        } else {
+            None
+        }
+    }
+
+    /// Current target
+    pub fn current(&self) -> BitrateDecision {
+        self.applied.unwrap_or_else(|| self.decision())
+    }
+
+    fn decision(&self) -> BitrateDecision {
+        let bitrate_kbps = self.target_kbps.round() as u32;
+        // Trade frame rate for sharpness first; text must stay readable
+        let fps = match bitrate_kbps {
+            0..=999 => 15,
+            1000..=2499 => 24,
+            _ => self.max_fps,
+        }
+        .min(self.max_fps);
+        let quality = match bitrate_kbps {
+            0..=2499 => StreamQuality::Low,
+            2500..=4999 => StreamQuality::Medium,
+            5000..=9999 => StreamQuality::High,
+            _ => StreamQuality::Ultra,
+        };
+        BitrateDecision { bitrate_kbps, fps, quality }
+    }
+}
+
+// =============================================================================
+// WebRTC Pump
+// =============================================================================
+
+/// Drive a negotiated WebRTC peer over a UDP socket until the stream ends,
+/// the peer fails, or ICE consent expires. An error return means the
+/// caller should fall back to WebSocket.
+pub async fn run_webrtc(
+    mut peer: WebRtcPeer,
+    socket: UdpSocket,
+    mut frames: broadcast::Receiver<StreamFrame>,
+    events: mpsc::UnboundedSender<TransportEvent>,
+) -> Result<(), TransportError> {
+    let mut buf = vec![0u8; 1500];
+    let mut consent = tokio::time::interval(Duration::from_secs(5));
+
+    let result = loop {
+        tokio::select! {
+            recv = socket.recv_from(&mut buf) => {
+                let (len, from) = recv.map_err(|e| TransportError::Io(e.to_string()))?;
+                match peer.handle_datagram(from, &buf[..len], Instant::now()) {
+                    Ok(output) => {
+                        for (to, datagram) in output.datagrams {
+                            let _ = socket.send_to(&datagram, to).await;
+                        }
+                        for event in output.events {
+                            let _ = events.send(event);
+                        }
+                    }
+                    // Unauthenticated checks and stray packets are dropped
+                    Err(WebRtcError::IceAuthFailed) | Err(WebRtcError::InvalidStun(_)) => {}
+                    Err(e) => break Err(TransportError::WebRtc(e)),
+                }
+            }
+            frame = frames.recv() => match frame {
+                Ok(StreamFrame::Video(frame)) => match peer.send_video(&frame) {
+                    Ok(datagrams) => {
+                        for (to, datagram) in datagrams {
+                            let _ = socket.send_to(&datagram, to).await;
+                        }
+                    }
+                    Err(WebRtcError::NotConnected) => {}
+                    Err(e) => break Err(TransportError::WebRtc(e)),
+                },
+                // Cursor/audio ride the data channel in the client; not carried here
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let _ = events.send(TransportEvent::KeyframeRequested);
+                }
+                Err(broadcast::error::RecvError::Closed) => break Ok(()),
+            },
+            _ = consent.tick() => {
+                if !peer.check_consent(Instant::now()) {
+                    break Err(TransportError::ConsentExpired);
+                }
+            }
+        }
+    };
+
+    let _ = events.send(TransportEvent::Closed {
+        kind: TransportKind::WebRtc,
+        reason: result.as_ref().err().map(|e| e.to_string()),
+    });
+    result
+}
+
+// =============================================================================
+// WebSocket Fallback
+// =============================================================================
+
+/// Stream frames over an accepted WebSocket. After a lag the stream skips
+/// ahead to the next keyframe instead of sending undecodable deltas.
+pub async fn run_websocket<S>(
+    mut ws: WebSocketStream<S>,
+    mut frames: broadcast::Receiver<StreamFrame>,
+    events: mpsc::UnboundedSender<TransportEvent>,
+) -> Result<(), TransportError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let _ = events.send(TransportEvent::Connected(TransportKind::WebSocket));
+    let _ = events.send(TransportEvent::KeyframeRequested);
+    let mut awaiting_keyframe = true;
+
+    let result = loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    if let StreamFrame::Video(v) = &frame {
+                        if awaiting_keyframe && !v.keyframe {
+                            continue;
+                        }
+                        awaiting_keyframe = false;
+                    }
+                    if let Some(message) = encode_frame(&frame) {
+                        if let Err(e) = ws.send(message).await {
+                            break Err(TransportError::WebSocket(e.to_string()));
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    awaiting_keyframe = true;
+                    let _ = events.send(TransportEvent::KeyframeRequested);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    let _ = ws.close(None).await;
+                    break Ok(());
+                }
+            },
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    for event in parse_client_feedback(&text) {
+                        let _ = events.send(event);
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(TransportError::WebSocket(e.to_string())),
+            }
+        }
+    };
+
+    let _ = events.send(TransportEvent::Closed {
+        kind: TransportKind::WebSocket,
+        reason: result.as_ref().err().map(|e| e.to_string()),
+    });
+    result
+}
+
+/// Binary framing for the WebSocket path. DOM updates go as JSON text.
+///
+/// ```text
+/// 'V' flags codec ts:u64 width:u16 height:u16 data...
+/// 'A' channels rate:u32 ts:u64 samples(i16 LE)...
+/// 'C' cursor x:f32 y:f32
+/// ```
+pub fn encode_frame(frame: &StreamFrame) -> Option<Message> {
+    match frame {
+        StreamFrame::Video(v) => {
+            let mut buf = Vec::with_capacity(v.data.len() + 15);
+            buf.push(b'V');
+            buf.push(v.keyframe as u8);
+            buf.push(match v.codec {
+                VideoCodec::H264 => 1,
+                VideoCodec::VP9 => 2,
+                VideoCodec::AV1 => 3,
+            });
+            buf.extend_from_slice(&v.timestamp.to_be_bytes());
+            buf.extend_from_slice(&(v.width as u16).to_be_bytes());
+            buf.extend_from_slice(&(v.height as u16).to_be_bytes());
+            buf.extend_from_slice(&v.data);
+            Some(Message::Binary(buf))
+        }
+        StreamFrame::Audio(a) => {
+            let mut buf = Vec::with_capacity(a.samples.len() * 2 + 14);
+            buf.push(b'A');
+            buf.push(a.channels);
+            buf.extend_from_slice(&a.sample_rate.to_be_bytes());
+            buf.extend_from_slice(&a.timestamp.to_be_bytes());
+            buf.extend(a.samples.iter().flat_map(|s| s.to_le_bytes()));
+            Some(Message::Binary(buf))
+        }
+        StreamFrame::Cursor(c) => {
+            let mut buf = Vec::with_capacity(10);
+            buf.push(b'C');
+            buf.push(cursor_code(c.cursor_type));
+            buf.extend_from_slice(&c.x.to_be_bytes());
+            buf.extend_from_slice(&c.y.to_be_bytes());
+            Some(Message::Binary(buf))
+        }
+        StreamFrame::Dom(update) => serde_json::to_string(update).ok().map(Message::Text),
+    }
+}
+
+fn cursor_code(cursor: CursorType) -> u8 {
+    match cursor {
+        CursorType::Default => 0,
+        CursorType::Pointer => 1,
+        CursorType::Text => 2,
+        CursorType::Wait => 3,
+        CursorType::Crosshair => 4,
+        CursorType::Move => 5,
+        CursorType::ResizeNS => 6,
+        CursorType::ResizeEW => 7,
+        CursorType::ResizeNESW => 8,
+        CursorType::ResizeNWSE => 9,
+        CursorType::NotAllowed => 10,
+        CursorType::Custom => 11,
+    }
+}
+
+/// Client feedback on the WebSocket path:
+/// `{"type":"keyframe"}` or `{"type":"stats","loss":0.02,"bandwidth_kbps":3000}`
+fn parse_client_feedback(text: &str) -> Vec<TransportEvent> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(text) else { return Vec::new() };
+    let mut events = Vec::new();
+
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("keyframe") => events.push(TransportEvent::KeyframeRequested),
+        Some("stats") => {
+            if let Some(loss) = value.get("loss").and_then(|l| l.as_f64()) {
+                events.push(TransportEvent::PacketLoss(loss));
+            }
+            if let Some(kbps) = value.get("bandwidth_kbps").and_then(|b| b.as_u64()) {
+                events.push(TransportEvent::BandwidthEstimate(kbps as u32));
+            }
+        }
+        _ => {}
+    }
+
+    events
+}
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(String),
+    WebRtc(WebRtcError),
+    WebSocket(String),
+    ConsentExpired,
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::WebRtc(e) => write!(f, "WebRTC error: {}", e),
+            Self::WebSocket(e) => write!(f, "WebSocket error: {}", e),
+            Self::ConsentExpired => write!(f, "ICE consent expired"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(bandwidth_kbps: f64, latency_ms: f64) -> SessionMetrics {
+        SessionMetrics { bandwidth_kbps, latency_ms, ..Default::default() }
+    }
+
+    #[test]
+    fn test_bitrate_follows_bandwidth_and_loss() {
+        let mut controller = BitrateController::new(&StreamConfig::default());
+        let first = controller.update(&metrics(20_000.0, 30.0)).unwrap();
+        assert_eq!(first.bitrate_kbps, 2700);
+
+        // Clean path ramps up to the configured max
+        for _ in 0..20 {
+            controller.update(&metrics(20_000.0, 30.0));
+        }
+        assert_eq!(controller.current().bitrate_kbps, 5000);
+        assert_eq!(controller.current().quality, StreamQuality::High);
+
+        // Measured bandwidth caps the target
+        let capped = controller.update(&metrics(2_000.0, 30.0)).unwrap();
+        assert_eq!(capped.bitrate_kbps, 1700);
+        assert_eq!(capped.fps, 24);
+
+        // Heavy loss backs off below the cap
+        controller.on_event(&TransportEvent::PacketLoss(0.3));
+        let backed_off = controller.update(&metrics(2_000.0, 30.0)).unwrap();
+        assert_eq!(backed_off.bitrate_kbps, 1445);
+    }
+
+    #[test]
+    fn test_bitrate_backs_off_on_queueing_delay() {
+        let mut controller = BitrateController::new(&StreamConfig::default());
+        controller.update(&metrics(0.0, 20.0));
+        let before = controller.current().bitrate_kbps;
+        controller.update(&metrics(0.0, 120.0));
+        assert!(controller.current().bitrate_kbps < before);
+    }
+}
@@ -0,0 +1,645 @@
+//! Upload Handling
+//!
+//! File upload interception for isolated sessions.
+//!
+//! The isolated browser intercepts file chooser dialogs (CDP
+//! `Page.fileChooserOpened`); the client sends the chosen file to the
+//! gateway instead of the page. Each upload runs through:
+//!
+//! ```text
+//! intercept ─► destination policy ─► DLP scan ─► watermark ─► release
+//!                     │                  │                      │
+//!                     └── blocked        └── approval ──────────┘
+//!                                          (manager / SOC)
+//! ```
+//!
+//! Released files are written to [`UPLOAD_DIR`] in the container and
+//! attached to the page's file input with `DOM.setFileInputFiles`.
+
+use crate::gateway::{FileSanitizer, FileType};
+use crate::policy::{DlpPolicy, Sensitivity, UrlCategory};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Directory released uploads are written to inside the container
+pub const UPLOAD_DIR: &str = "/uploads";
+
+/// Upload manager with destination policy, DLP and approvals
+pub struct UploadManager {
+    config: UploadConfig,
+    dlp: DlpPolicy,
+    sanitizer: FileSanitizer,
+    pending: dashmap::DashMap<String, PendingUpload>,
+    audit: parking_lot::RwLock<Vec<UploadAudit>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadConfig {
+    /// Max file size (bytes)
+    pub max_file_size: u64,
+    /// Action when no rule matches
+    pub default_action: UploadAction,
+    /// Per destination domain rules (suffix match, first match wins)
+    pub domain_rules: Vec<UploadRule>,
+    /// Per destination category actions
+    pub category_rules: HashMap<UrlCategory, UploadAction>,
+    /// Scan upload content for sensitive data
+    pub dlp_scanning: bool,
+    /// DLP findings at or above this level need approval
+    pub approval_sensitivity: Sensitivity,
+    /// Critical DLP findings are blocked instead of sent for approval
+    pub block_critical: bool,
+    /// Who approves DLP-flagged uploads
+    pub dlp_approver: ApproverRole,
+    /// Stamp released files with user/session identity
+    pub watermark: bool,
+    /// Pending approvals expire after this long
+    pub approval_timeout: Duration,
+    /// Audit records kept in memory
+    pub audit_retention: usize,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        let mut category_rules = HashMap::new();
+        category_rules.insert(UrlCategory::Malware, UploadAction::Block);
+        category_rules.insert(UrlCategory::Phishing, UploadAction::Block);
+        
+        Self {
+            max_file_size: 50 * 1024 * 1024, // 50 MB
+            default_action: UploadAction::Allow,
+            domain_rules: Vec::new(),
+            category_rules,
+            dlp_scanning: true,
+            approval_sensitivity: Sensitivity::High,
+            block_critical: false,
+            dlp_approver: ApproverRole::Soc,
+            watermark: false,
+            approval_timeout: Duration::from_secs(24 * 3600),
+            audit_retention: 10_000,
+        }
+    }
+}
+
+impl UploadConfig {
+    /// Action for a destination: domain rules, then category, then default
+    pub fn action_for(&self, domain: &str, category: Option<UrlCategory>) -> UploadAction {
+        if let Some(rule) = self.domain_rules.iter()
+            .find(|r| domain == r.domain || domain.ends_with(&format!(".{}", r.domain))) {
+            return rule.action;
+        }
+        category
+            .and_then(|c| self.category_rules.get(&c).copied())
+            .unwrap_or(self.default_action)
+    }
+}
+
+/// Destination domain rule
+#[derive(Debug, Clone)]
+pub struct UploadRule {
+    pub domain: String,
+    pub action: UploadAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum UploadAction {
+    /// Release after DLP
+    Allow,
+    /// Never release
+    Block,
+    /// Hold until approved
+    RequireApproval(ApproverRole),
+}
+
+/// Who may release a held upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ApproverRole {
+    /// User's manager
+    Manager,
+    /// Security operations
+    Soc,
+}
+
+impl ApproverRole {
+    /// SOC analysts may also act on manager approvals
+    pub fn can_approve(&self, required: ApproverRole) -> bool {
+        *self == required || *self == ApproverRole::Soc
+    }
+}
+
+// =============================================================================
+// Browser events
+// =============================================================================
+
+/// File chooser opened in the isolated page (CDP `Page.fileChooserOpened`)
+#[derive(Debug, Clone)]
+pub struct FileChooserEvent {
+    pub frame_id: String,
+    pub backend_node_id: i64,
+    pub multiple: bool,
+}
+
+impl FileChooserEvent {
+    /// Parse a CDP event message
+    pub fn from_cdp(message: &serde_json::Value) -> Option<Self> {
+        if message.get("method")?.as_str()? != "Page.fileChooserOpened" {
+            return None;
+        }
+        let params = message.get("params")?;
+        Some(Self {
+            frame_id: params.get("frameId")?.as_str()?.to_string(),
+            backend_node_id: params.get("backendNodeId")?.as_i64()?,
+            multiple: params.get("mode").and_then(|m| m.as_str()) == Some("selectMultiple"),
+        })
+    }
+    
+    /// CDP command making Chromium report file choosers instead of showing them
+    pub fn intercept_file_chooser() -> serde_json::Value {
+        serde_json::json!({
+            "method": "Page.setInterceptFileChooserDialog",
+            "params": { "enabled": true }
+        })
+    }
+    
+    /// CDP command attaching released files to the file input
+    pub fn set_files(backend_node_id: i64, paths: &[String]) -> serde_json::Value {
+        serde_json::json!({
+            "method": "DOM.setFileInputFiles",
+            "params": { "files": paths, "backendNodeId": backend_node_id }
+        })
+    }
+}
+
+// =============================================================================
+// Pipeline types
+// =============================================================================
+
+/// File the user wants to upload from their device
+#[derive(Debug, Clone)]
+pub struct UploadRequest {
+    pub session_id: String,
+    pub user_id: String,
+    /// Page the file is being uploaded to
+    pub destination_url: String,
+    /// Destination category (from SWG categorization)
+    pub category: Option<UrlCategory>,
+    /// File input the upload is for
+    pub backend_node_id: i64,
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingUpload {
+    request: UploadRequest,
+    /// File after watermarking
+    data: Vec<u8>,
+    status: UploadStatus,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum UploadStatus {
+    /// Waiting for approval
+    AwaitingApproval { approver: ApproverRole },
+    /// Ready to attach in the container
+    Released,
+    Blocked { reason: String },
+    Rejected { reason: String },
+    Expired,
+}
+
+/// DLP finding in upload content
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadFinding {
+    pub pattern: String,
+    pub sensitivity: String,
+    pub preview: String,
+}
+
+/// Approval decision on a held upload
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRecord {
+    pub approver_id: String,
+    pub role: ApproverRole,
+    pub approved: bool,
+    pub comment: Option<String>,
+    pub decided_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Audit record for an upload
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadAudit {
+    pub upload_id: String,
+    pub session_id: String,
+    pub user_id: String,
+    pub destination_url: String,
+    pub category: Option<String>,
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+    pub action: UploadAction,
+    pub status: UploadStatus,
+    pub findings: Vec<UploadFinding>,
+    pub watermarked: bool,
+    pub approval: Option<ApprovalRecord>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Upload cleared for the container
+#[derive(Debug, Clone)]
+pub struct ReleasedUpload {
+    pub upload_id: String,
+    pub session_id: String,
+    pub backend_node_id: i64,
+    /// Path to write the file to inside the container
+    pub container_path: String,
+    pub data: Vec<u8>,
+}
+
+impl UploadManager {
+    pub fn new(config: UploadConfig) -> Self {
+        Self {
+            config,
+            dlp: DlpPolicy::new(),
+            sanitizer: FileSanitizer::new(),
+            pending: dashmap::DashMap::new(),
+            audit: parking_lot::RwLock::new(Vec::new()),
+        }
+    }
+    
+    /// Use a tenant DLP policy
+    pub fn with_dlp(mut self, dlp: DlpPolicy) -> Self {
+        self.dlp = dlp;
+        self
+    }
+    
+    /// Run an upload through policy, DLP and watermarking. Returns the
+    /// audit record; the status says whether it was released, blocked or
+    /// is waiting for approval.
+    pub fn submit(&self, request: UploadRequest) -> Result<UploadAudit, UploadError> {
+        let size = request.data.len() as u64;
+        if size > self.config.max_file_size {
+            return Err(UploadError::TooLarge(size, self.config.max_file_size));
+        }
+        
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now();
+        let domain = extract_domain(&request.destination_url);
+        let action = self.config.action_for(&domain, request.category);
+        
+        let findings = if self.config.dlp_scanning { self.scan(&request.data) } else { Vec::new() };
+        let worst = findings.iter().map(|(_, s)| *s).max();
+        let findings: Vec<UploadFinding> = findings.into_iter().map(|(f, _)| f).collect();
+        
+        let status = match action {
+            UploadAction::Block => UploadStatus::Blocked {
+                reason: format!("Uploads to {} are not allowed", domain),
+            },
+            _ if self.config.block_critical && worst == Some(Sensitivity::Critical) => UploadStatus::Blocked {
+                reason: "Critical sensitive data detected".to_string(),
+            },
+            UploadAction::RequireApproval(approver) => UploadStatus::AwaitingApproval { approver },
+            UploadAction::Allow if worst.is_some_and(|s| s >= self.config.approval_sensitivity) => {
+                UploadStatus::AwaitingApproval { approver: self.config.dlp_approver }
+            }
+            UploadAction::Allow => UploadStatus::Released,
+        };
+        
+        let (data, watermarked) = if self.config.watermark && !matches!(status, UploadStatus::Blocked { .. }) {
+            self.watermark(&request, &upload_id, now)
+        } else {
+            (request.data.clone(), false)
+        };
+        
+        let audit = UploadAudit {
+            upload_id: upload_id.clone(),
+            session_id: request.session_id.clone(),
+            user_id: request.user_id.clone(),
+            destination_url: request.destination_url.clone(),
+            category: request.category.map(|c| format!("{:?}", c)),
+            filename: request.filename.clone(),
+            size,
+            sha256: sha256_hex(&request.data),
+            action,
+            status: status.clone(),
+            findings,
+            watermarked,
+            approval: None,
+            created_at: now,
+            updated_at: now,
+        };
+        
+        match &status {
+            UploadStatus::Blocked { reason } => {
+                tracing::warn!("Upload {} from session {} blocked: {}", request.filename, request.session_id, reason);
+            }
+            UploadStatus::AwaitingApproval { approver } => {
+                tracing::info!("Upload {} from session {} held for {:?} approval", request.filename, request.session_id, approver);
+            }
+            _ => {}
+        }
+        
+        if !matches!(status, UploadStatus::Blocked { .. }) {
+            self.pending.insert(upload_id, PendingUpload { request, data, status, created_at: now });
+        }
+        self.push_audit(audit.clone());
+        Ok(audit)
+    }
+    
+    /// Approve or reject a held upload
+    pub fn decide(
+        &self,
+        upload_id: &str,
+        approver_id: &str,
+        role: ApproverRole,
+        approved: bool,
+        comment: Option<String>,
+    ) -> Result<UploadAudit, UploadError> {
+        let mut pending = self.pending.get_mut(upload_id).ok_or(UploadError::NotFound)?;
+        let required = match pending.status {
+            UploadStatus::AwaitingApproval { approver } => approver,
+            _ => return Err(UploadError::NotAwaitingApproval),
+        };
+        if !role.can_approve(required) {
+            return Err(UploadError::NotAuthorized(required));
+        }
+        if approver_id == pending.request.user_id {
+            return Err(UploadError::SelfApproval);
+        }
+        
+        pending.status = if approved {
+            UploadStatus::Released
+        } else {
+            UploadStatus::Rejected {
+                reason: comment.clone().unwrap_or_else(|| "Rejected by approver".to_string()),
+            }
+        };
+        let status = pending.status.clone();
+        drop(pending);
+        if !approved {
+            self.pending.remove(upload_id);
+        }
+        
+        let record = ApprovalRecord {
+            approver_id: approver_id.to_string(),
+            role,
+            approved,
+            comment,
+            decided_at: chrono::Utc::now(),
+        };
+        self.update_audit(upload_id, status, Some(record)).ok_or(UploadError::NotFound)
+    }
+    
+    /// Uploads waiting for a role's approval
+    pub fn pending_approvals(&self, role: ApproverRole) -> Vec<UploadAudit> {
+        let ids: Vec<String> = self.pending.iter()
+            .filter(|p| matches!(p.status, UploadStatus::AwaitingApproval { approver } if role.can_approve(approver)))
+            .map(|p| p.key().clone())
+            .collect();
+        self.audit.read().iter()
+            .filter(|a| ids.contains(&a.upload_id))
+            .cloned()
+            .collect()
+    }
+    
+    /// Take a released upload for attaching in the container
+    pub fn take_released(&self, upload_id: &str) -> Result<ReleasedUpload, UploadError> {
+        match self.pending.get(upload_id).map(|p| p.status.clone()) {
+            None => return Err(UploadError::NotFound),
+            Some(UploadStatus::Released) => {}
+            Some(UploadStatus::AwaitingApproval { .. }) => return Err(UploadError::AwaitingApproval),
+            Some(_) => return Err(UploadError::NotFound),
+        }
+        let (_, pending) = self.pending.remove(upload_id).ok_or(UploadError::NotFound)?;
+        
+        Ok(ReleasedUpload {
+            upload_id: upload_id.to_string(),
+            session_id: pending.request.session_id,
+            backend_node_id: pending.request.backend_node_id,
+            container_path: format!("{}/{}", UPLOAD_DIR, sanitize_filename(&pending.request.filename)),
+            data: pending.data,
+        })
+    }
+    
+    /// Session an upload belongs to
+    pub fn session_of(&self, upload_id: &str) -> Option<String> {
+        self.pending.get(upload_id).map(|p| p.request.session_id.clone())
+    }
+    
+    /// Audit records for a session
+    pub fn audit_log(&self, session_id: &str) -> Vec<UploadAudit> {
+        self.audit.read().iter()
+            .filter(|a| a.session_id == session_id)
+            .cloned()
+            .collect()
+    }
+    
+    /// Expire approvals that were not decided in time
+    pub fn cleanup(&self) {
+        let timeout = chrono::Duration::from_std(self.config.approval_timeout)
+            .unwrap_or_else(|_| chrono::Duration::hours(24));
+        let cutoff = chrono::Utc::now() - timeout;
+        
+        let expired: Vec<String> = self.pending.iter()
+            .filter(|p| matches!(p.status, UploadStatus::AwaitingApproval { .. }) && p.created_at < cutoff)
+            .map(|p| p.key().clone())
+            .collect();
+        for id in expired {
+            self.pending.remove(&id);
+            self.update_audit(&id, UploadStatus::Expired, None);
+        }
+    }
+    
+    /// DLP scan of the file's text content
+    fn scan(&self, data: &[u8]) -> Vec<(UploadFinding, Sensitivity)> {
+        // Binary formats are scanned on their printable runs; compressed
+        // containers (OOXML, archives) are not unpacked
+        let text = String::from_utf8_lossy(data);
+        self.dlp.check(&text).into_iter()
+            .map(|v| (UploadFinding {
+                pattern: v.pattern_name,
+                sensitivity: format!("{:?}", v.sensitivity),
+                preview: mask(&v.content_preview),
+            }, v.sensitivity))
+            .collect()
+    }
+    
+    /// Stamp the file with who uploaded it where. Text files get a footer
+    /// line, PDFs a trailing comment after `%%EOF`; other formats are left
+    /// unchanged.
+    fn watermark(&self, request: &UploadRequest, upload_id: &str, at: chrono::DateTime<chrono::Utc>) -> (Vec<u8>, bool) {
+        let mark = format!(
+            "OpenSASE upload {} user={} session={} at={}",
+            upload_id, request.user_id, request.session_id, at.to_rfc3339()
+        );
+        let mut data = request.data.clone();
+        
+        match self.sanitizer.detect_type(&request.data) {
+            FileType::Pdf => {
+                data.extend_from_slice(format!("\n% {}\n", mark).as_bytes());
+                (data, true)
+            }
+            FileType::Unknown if std::str::from_utf8(&request.data).is_ok() => {
+                if !data.ends_with(b"\n") && !data.is_empty() {
+                    data.push(b'\n');
+                }
+                data.extend_from_slice(format!("# {}\n", mark).as_bytes());
+                (data, true)
+            }
+            _ => (data, false),
+        }
+    }
+    
+    fn update_audit(&self, upload_id: &str, status: UploadStatus, approval: Option<ApprovalRecord>) -> Option<UploadAudit> {
+        let mut audit = self.audit.write();
+        let record = audit.iter_mut().rev().find(|a| a.upload_id == upload_id)?;
+        record.status = status;
+        if approval.is_some() {
+            record.approval = approval;
+        }
+        record.updated_at = chrono::Utc::now();
+        Some(record.clone())
+    }
+    
+    fn push_audit(&self, record: UploadAudit) {
+        let mut audit = self.audit.write();
+        audit.push(record);
+        let excess = audit.len().saturating_sub(self.config.audit_retention);
+        if excess > 0 {
+            audit.drain(..excess);
+        }
+    }
+}
+
+impl Default for UploadManager {
+    fn default() -> Self {
+        Self::new(UploadConfig::default())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UploadError {
+    TooLarge(u64, u64),
+    NotFound,
+    AwaitingApproval,
+    NotAwaitingApproval,
+    NotAuthorized(ApproverRole),
+    SelfApproval,
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge(size, max) => write!(f, "File too large: {} > {}", size, max),
+            Self::NotFound => write!(f, "Upload not found"),
+            Self::AwaitingApproval => write!(f, "Upload awaiting approval"),
+            Self::NotAwaitingApproval => write!(f, "Upload is not awaiting approval"),
+            Self::NotAuthorized(role) => write!(f, "Approval requires {:?}", role),
+            Self::SelfApproval => write!(f, "Users cannot approve their own uploads"),
+        }
+    }
+}
+
+fn extract_domain(url: &str) -> String {
+    url.trim_start_matches("http://")
+        .trim_start_matches("https://")
+        .split('/')
+        .next()
+        .unwrap_or("")
+        .split(':')
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Keep the first and last two characters of a finding preview
+fn mask(preview: &str) -> String {
+    let chars: Vec<char> = preview.chars().collect();
+    if chars.len() <= 4 {
+        return "*".repeat(chars.len());
+    }
+    let mut masked: String = chars[..2].iter().collect();
+    masked.push_str(&"*".repeat(chars.len() - 4));
+    masked.extend(&chars[chars.len() - 2..]);
+    masked
+}
+
+fn sanitize_filename(name: &str) -> String {
+    let cleaned: String = name.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '.' || *c == '-' || *c == '_')
+        .collect();
+    if cleaned.trim_matches('.').is_empty() {
+        "upload".to_string()
+    } else {
+        cleaned
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn request(url: &str, data: &[u8]) -> UploadRequest {
+        UploadRequest {
+            session_id: "session-1".to_string(),
+            user_id: "alice".to_string(),
+            destination_url: url.to_string(),
+            category: Some(UrlCategory::Business),
+            backend_node_id: 42,
+            filename: "notes.txt".to_string(),
+            data: data.to_vec(),
+        }
+    }
+    
+    #[test]
+    fn test_domain_policy_and_release() {
+        let manager = UploadManager::new(UploadConfig {
+            domain_rules: vec![UploadRule {
+                domain: "pastebin.com".to_string(),
+                action: UploadAction::Block,
+            }],
+            watermark: true,
+            ..Default::default()
+        });
+        
+        let blocked = manager.submit(request("https://pastebin.com/new", b"hello")).unwrap();
+        assert!(matches!(blocked.status, UploadStatus::Blocked { .. }));
+        
+        let allowed = manager.submit(request("https://crm.example.com/attach", b"hello")).unwrap();
+        assert_eq!(allowed.status, UploadStatus::Released);
+        assert!(allowed.watermarked);
+        
+        let released = manager.take_released(&allowed.upload_id).unwrap();
+        assert_eq!(released.container_path, "/uploads/notes.txt");
+        assert!(String::from_utf8(released.data).unwrap().contains("user=alice"));
+    }
+    
+    #[test]
+    fn test_sensitive_upload_requires_soc_approval() {
+        let manager = UploadManager::default();
+        let audit = manager
+            .submit(request("https://files.example.com/", b"card 4111-1111-1111-1111"))
+            .unwrap();
+        
+        assert_eq!(audit.status, UploadStatus::AwaitingApproval { approver: ApproverRole::Soc });
+        assert!(!audit.findings.is_empty());
+        assert!(matches!(manager.take_released(&audit.upload_id), Err(UploadError::AwaitingApproval)));
+        assert_eq!(manager.pending_approvals(ApproverRole::Soc).len(), 1);
+        
+        assert!(matches!(
+            manager.decide(&audit.upload_id, "bob", ApproverRole::Manager, true, None),
+            Err(UploadError::NotAuthorized(ApproverRole::Soc))
+        ));
+        let decided = manager.decide(&audit.upload_id, "soc-analyst", ApproverRole::Soc, true, None).unwrap();
+        assert_eq!(decided.status, UploadStatus::Released);
+        assert!(manager.take_released(&audit.upload_id).is_ok());
+    }
+}
@@ -0,0 +1,1298 @@
+//! WebRTC Transport
+//!
+//! Pixel-push delivery over WebRTC: SDP offer/answer negotiation, an ICE-lite
+//! agent answering STUN connectivity checks, DTLS-SRTP keying and RTP
+//! payloading for H.264/VP9. Everything here is sans-IO; the UDP pump lives
+//! in `transport`. The DTLS record layer is supplied through [`DtlsSrtp`] so
+//! the media plane can plug in its TLS stack, while this module enforces the
+//! negotiated role and certificate fingerprint.
+
+use crate::encoder::RtpPacket;
+use crate::streaming::VideoFrame;
+use crate::transport::{TransportEvent, TransportKind};
+use crate::VideoCodec;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+/// ICE consent freshness timeout (RFC 7675)
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Packets kept for NACK retransmission
+const RTX_HISTORY: usize = 512;
+/// Room left for the SRTP auth tag
+const SRTP_OVERHEAD: usize = 16;
+
+// =============================================================================
+// SDP
+// =============================================================================
+
+/// Parsed session description
+#[derive(Debug, Clone, Default)]
+pub struct SessionDescription {
+    pub ice_ufrag: Option<String>,
+    pub ice_pwd: Option<String>,
+    pub fingerprint: Option<Fingerprint>,
+    pub setup: Option<DtlsSetup>,
+    pub media: Vec<MediaDescription>,
+}
+
+/// One m= section
+#[derive(Debug, Clone, Default)]
+pub struct MediaDescription {
+    pub kind: String,
+    pub protocol: String,
+    /// Raw format list from the m= line
+    pub format_ids: Vec<String>,
+    pub mid: Option<String>,
+    pub formats: Vec<RtpFormat>,
+    pub candidates: Vec<IceCandidate>,
+    pub ice_ufrag: Option<String>,
+    pub ice_pwd: Option<String>,
+    pub fingerprint: Option<Fingerprint>,
+    pub setup: Option<DtlsSetup>,
+}
+
+/// Payload type mapping (rtpmap + fmtp + rtcp-fb)
+#[derive(Debug, Clone)]
+pub struct RtpFormat {
+    pub payload_type: u8,
+    pub encoding: String,
+    pub clock_rate: u32,
+    pub fmtp: Option<String>,
+    pub feedback: Vec<String>,
+}
+
+/// Certificate fingerprint (a=fingerprint)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub algorithm: String,
+    /// Colon-separated uppercase hex
+    pub value: String,
+}
+
+impl Fingerprint {
+    pub fn new(algorithm: &str, value: &str) -> Self {
+        Self {
+            algorithm: algorithm.to_ascii_lowercase(),
+            value: value.to_ascii_uppercase(),
+        }
+    }
+}
+
+/// DTLS setup attribute (RFC 4145/5763)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtlsSetup {
+    Active,
+    Passive,
+    ActPass,
+}
+
+/// Our side of the DTLS handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtlsRole {
+    Client,
+    Server,
+}
+
+impl SessionDescription {
+    /// Parse an SDP blob
+    pub fn parse(sdp: &str) -> Result<Self, WebRtcError> {
+        let mut desc = SessionDescription::default();
+
+        for line in sdp.lines().map(str::trim_end) {
+            let Some((key, value)) = line.split_once('=') else { continue };
+
+            if key == "m" {
+                let mut parts = value.split_whitespace();
+                let kind = parts.next().unwrap_or_default().to_string();
+                let _port = parts.next();
+                let protocol = parts.next().unwrap_or_default().to_string();
+                desc.media.push(MediaDescription {
+                    kind,
+                    protocol,
+                    format_ids: parts.map(str::to_string).collect(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            if key != "a" {
+                continue;
+            }
+
+            let (attr, arg) = value.split_once(':').unwrap_or((value, ""));
+            let media = desc.media.last_mut();
+
+            match (attr, media) {
+                ("ice-ufrag", None) => desc.ice_ufrag = Some(arg.to_string()),
+                ("ice-ufrag", Some(m)) => m.ice_ufrag = Some(arg.to_string()),
+                ("ice-pwd", None) => desc.ice_pwd = Some(arg.to_string()),
+                ("ice-pwd", Some(m)) => m.ice_pwd = Some(arg.to_string()),
+                ("fingerprint", media) => {
+                    let (alg, fp) = arg.split_once(' ')
+                        .ok_or_else(|| WebRtcError::InvalidSdp(format!("bad fingerprint: {}", arg)))?;
+                    let fp = Some(Fingerprint::new(alg, fp.trim()));
+                    match media {
+                        Some(m) => m.fingerprint = fp,
+                        None => desc.fingerprint = fp,
+                    }
+                }
+                ("setup", media) => {
+                    let setup = match arg {
+                        "active" => DtlsSetup::Active,
+                        "passive" => DtlsSetup::Passive,
+                        "actpass" => DtlsSetup::ActPass,
+                        other => return Err(WebRtcError::InvalidSdp(format!("bad setup: {}", other))),
+                    };
+                    match media {
+                        Some(m) => m.setup = Some(setup),
+                        None => desc.setup = Some(setup),
+                    }
+                }
+                ("mid", Some(m)) => m.mid = Some(arg.to_string()),
+                ("rtpmap", Some(m)) => {
+                    let (pt, encoding) = arg.split_once(' ')
+                        .ok_or_else(|| WebRtcError::InvalidSdp(format!("bad rtpmap: {}", arg)))?;
+                    let mut enc = encoding.split('/');
+                    m.formats.push(RtpFormat {
+                        payload_type: pt.parse()
+                            .map_err(|_| WebRtcError::InvalidSdp(format!("bad payload type: {}", pt)))?,
+                        encoding: enc.next().unwrap_or_default().to_string(),
+                        clock_rate: enc.next().and_then(|c| c.parse().ok()).unwrap_or(90000),
+                        fmtp: None,
+                        feedback: Vec::new(),
+                    });
+                }
+                ("fmtp", Some(m)) => {
+                    if let Some((pt, params)) = arg.split_once(' ') {
+                        if let Some(f) = m.formats.iter_mut().find(|f| f.payload_type.to_string() == pt) {
+                            f.fmtp = Some(params.to_string());
+                        }
+                    }
+                }
+                ("rtcp-fb", Some(m)) => {
+                    if let Some((pt, fb)) = arg.split_once(' ') {
+                        for f in m.formats.iter_mut().filter(|f| pt == "*" || f.payload_type.to_string() == pt) {
+                            f.feedback.push(fb.to_string());
+                        }
+                    }
+                }
+                ("candidate", Some(m)) => {
+                    // Unparseable candidates (e.g. mDNS hostnames) are skipped
+                    if let Ok(c) = IceCandidate::parse(arg) {
+                        m.candidates.push(c);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if desc.media.is_empty() {
+            return Err(WebRtcError::InvalidSdp("no media sections".to_string()));
+        }
+        Ok(desc)
+    }
+}
+
+/// Local ICE credentials, certificate and candidates
+#[derive(Debug, Clone)]
+pub struct LocalEndpoint {
+    pub ice_ufrag: String,
+    pub ice_pwd: String,
+    /// SHA-256 fingerprint of the media plane's DTLS certificate
+    pub fingerprint: Fingerprint,
+    pub candidates: Vec<IceCandidate>,
+    pub ssrc: u32,
+}
+
+impl LocalEndpoint {
+    /// Fresh credentials with one host candidate per media address
+    pub fn new(fingerprint: Fingerprint, media_addrs: &[SocketAddr]) -> Self {
+        let ufrag = uuid::Uuid::new_v4().simple().to_string();
+        Self {
+            ice_ufrag: ufrag[..8].to_string(),
+            ice_pwd: uuid::Uuid::new_v4().simple().to_string(),
+            fingerprint,
+            candidates: media_addrs.iter()
+                .enumerate()
+                .map(|(i, addr)| IceCandidate::host(*addr, 65535 - i as u16))
+                .collect(),
+            ssrc: random_u32(),
+        }
+    }
+}
+
+/// Result of offer/answer negotiation
+#[derive(Debug, Clone)]
+pub struct NegotiatedStream {
+    pub mid: String,
+    pub codec: VideoCodec,
+    pub payload_type: u8,
+    pub local_ice_ufrag: String,
+    pub local_ice_pwd: String,
+    pub remote_ice_ufrag: String,
+    pub remote_ice_pwd: String,
+    pub remote_fingerprint: Fingerprint,
+    pub dtls_role: DtlsRole,
+    pub remote_candidates: Vec<IceCandidate>,
+    pub ssrc: u32,
+    /// Client accepts NACK retransmissions
+    pub nack: bool,
+}
+
+/// Answer a client's offer for a send-only pixel stream.
+///
+/// The first video section using DTLS-SRTP carries the stream; other
+/// sections are rejected. `preferred` is used when offered, otherwise the
+/// first payloadable codec in the client's order.
+pub fn negotiate(
+    offer: &str,
+    local: &LocalEndpoint,
+    preferred: VideoCodec,
+) -> Result<(String, NegotiatedStream), WebRtcError> {
+    let desc = SessionDescription::parse(offer)?;
+
+    let index = desc.media.iter()
+        .position(|m| m.kind == "video")
+        .ok_or(WebRtcError::NoVideoMedia)?;
+    let video = &desc.media[index];
+
+    if !video.protocol.contains("TLS/RTP/SAVPF") {
+        return Err(WebRtcError::InsecureProfile(video.protocol.clone()));
+    }
+
+    let remote_ufrag = video.ice_ufrag.clone().or_else(|| desc.ice_ufrag.clone());
+    let remote_pwd = video.ice_pwd.clone().or_else(|| desc.ice_pwd.clone());
+    let (Some(remote_ice_ufrag), Some(remote_ice_pwd)) = (remote_ufrag, remote_pwd) else {
+        return Err(WebRtcError::MissingIceCredentials);
+    };
+    let remote_fingerprint = video.fingerprint.clone()
+        .or_else(|| desc.fingerprint.clone())
+        .filter(|f| f.algorithm == "sha-256")
+        .ok_or(WebRtcError::MissingFingerprint)?;
+
+    // We stay DTLS server unless the client insists on it
+    let (our_setup, dtls_role) = match video.setup.or(desc.setup).unwrap_or(DtlsSetup::ActPass) {
+        DtlsSetup::Passive => ("active", DtlsRole::Client),
+        DtlsSetup::Active | DtlsSetup::ActPass => ("passive", DtlsRole::Server),
+    };
+
+    let format = select_format(&video.formats, preferred).ok_or(WebRtcError::UnsupportedCodec)?;
+    let codec = codec_of(&format.encoding).ok_or(WebRtcError::UnsupportedCodec)?;
+    let mid = video.mid.clone().unwrap_or_else(|| index.to_string());
+    let nack = format.feedback.iter().any(|f| f == "nack");
+
+    let mut sdp = String::new();
+    sdp.push_str("v=0\r\n");
+    sdp.push_str(&format!("o=- {} 2 IN IP4 127.0.0.1\r\n", random_u32()));
+    sdp.push_str("s=OSBI Stream\r\n");
+    sdp.push_str("t=0 0\r\n");
+    sdp.push_str(&format!("a=group:BUNDLE {}\r\n", mid));
+    sdp.push_str("a=ice-lite\r\n");
+
+    for (i, media) in desc.media.iter().enumerate() {
+        if i != index {
+            sdp.push_str(&format!("m={} 0 {} {}\r\n", media.kind, media.protocol, media.format_ids.join(" ")));
+            if let Some(mid) = &media.mid {
+                sdp.push_str(&format!("a=mid:{}\r\n", mid));
+            }
+            sdp.push_str("a=inactive\r\n");
+            continue;
+        }
+
+        sdp.push_str(&format!("m=video 9 {} {}\r\n", media.protocol, format.payload_type));
+        sdp.push_str("c=IN IP4 0.0.0.0\r\n");
+        sdp.push_str(&format!("a=mid:{}\r\n", mid));
+        sdp.push_str("a=sendonly\r\n");
+        sdp.push_str("a=rtcp-mux\r\n");
+        sdp.push_str(&format!("a=ice-ufrag:{}\r\n", local.ice_ufrag));
+        sdp.push_str(&format!("a=ice-pwd:{}\r\n", local.ice_pwd));
+        sdp.push_str(&format!("a=fingerprint:{} {}\r\n", local.fingerprint.algorithm, local.fingerprint.value));
+        sdp.push_str(&format!("a=setup:{}\r\n", our_setup));
+        sdp.push_str(&format!("a=rtpmap:{} {}/{}\r\n", format.payload_type, format.encoding, format.clock_rate));
+        if let Some(fmtp) = &format.fmtp {
+            sdp.push_str(&format!("a=fmtp:{} {}\r\n", format.payload_type, fmtp));
+        }
+        for fb in &format.feedback {
+            if matches!(fb.as_str(), "nack" | "nack pli" | "ccm fir" | "goog-remb") {
+                sdp.push_str(&format!("a=rtcp-fb:{} {}\r\n", format.payload_type, fb));
+            }
+        }
+        sdp.push_str(&format!("a=ssrc:{} cname:osbi\r\n", local.ssrc));
+        for candidate in &local.candidates {
+            sdp.push_str(&format!("a={}\r\n", candidate.to_sdp()));
+        }
+        sdp.push_str("a=end-of-candidates\r\n");
+    }
+
+    let negotiated = NegotiatedStream {
+        mid,
+        codec,
+        payload_type: format.payload_type,
+        local_ice_ufrag: local.ice_ufrag.clone(),
+        local_ice_pwd: local.ice_pwd.clone(),
+        remote_ice_ufrag,
+        remote_ice_pwd,
+        remote_fingerprint,
+        dtls_role,
+        remote_candidates: video.candidates.clone(),
+        ssrc: local.ssrc,
+        nack,
+    };
+
+    Ok((sdp, negotiated))
+}
+
+fn codec_of(encoding: &str) -> Option<VideoCodec> {
+    match encoding.to_ascii_uppercase().as_str() {
+        "H264" => Some(VideoCodec::H264),
+        "VP9" => Some(VideoCodec::VP9),
+        // AV1 needs OBU-aware payloading which we don't do yet
+        _ => None,
+    }
+}
+
+fn select_format(formats: &[RtpFormat], preferred: VideoCodec) -> Option<&RtpFormat> {
+    // Browsers list packetization-mode=0 H.264 too; FU-A needs mode 1
+    let usable = |f: &&RtpFormat| match codec_of(&f.encoding) {
+        Some(VideoCodec::H264) => f.fmtp.as_deref().is_some_and(|p| p.contains("packetization-mode=1")),
+        Some(_) => true,
+        None => false,
+    };
+
+    formats.iter()
+        .filter(usable)
+        .find(|f| codec_of(&f.encoding) == Some(preferred))
+        .or_else(|| formats.iter().find(usable))
+}
+
+// =============================================================================
+// ICE
+// =============================================================================
+
+/// ICE candidate (RFC 8445 / 8839)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceCandidate {
+    pub foundation: String,
+    pub component: u16,
+    pub transport: String,
+    pub priority: u32,
+    pub address: SocketAddr,
+    pub kind: CandidateType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateType {
+    Host,
+    ServerReflexive,
+    PeerReflexive,
+    Relay,
+}
+
+impl CandidateType {
+    fn type_preference(self) -> u32 {
+        match self {
+            Self::Host => 126,
+            Self::PeerReflexive => 110,
+            Self::ServerReflexive => 100,
+            Self::Relay => 0,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Host => "host",
+            Self::ServerReflexive => "srflx",
+            Self::PeerReflexive => "prflx",
+            Self::Relay => "relay",
+        }
+    }
+}
+
+impl IceCandidate {
+    /// Host candidate for a local media address
+    pub fn host(address: SocketAddr, local_preference: u16) -> Self {
+        Self {
+            foundation: "1".to_string(),
+            component: 1,
+            transport: "udp".to_string(),
+            priority: Self::compute_priority(CandidateType::Host, local_preference, 1),
+            address,
+            kind: CandidateType::Host,
+        }
+    }
+
+    /// RFC 8445 §5.1.2.1 priority
+    pub fn compute_priority(kind: CandidateType, local_preference: u16, component: u16) -> u32 {
+        (kind.type_preference() << 24) + ((local_preference as u32) << 8) + (256 - component as u32)
+    }
+
+    /// Parse `candidate:...` (with or without the `candidate:` prefix)
+    pub fn parse(line: &str) -> Result<Self, WebRtcError> {
+        let line = line.trim().trim_start_matches("a=").trim_start_matches("candidate:");
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let bad = || WebRtcError::InvalidSdp(format!("bad candidate: {}", line));
+
+        if parts.len() < 8 || parts[6] != "typ" {
+            return Err(bad());
+        }
+        let ip: IpAddr = parts[4].parse().map_err(|_| bad())?;
+        let port: u16 = parts[5].parse().map_err(|_| bad())?;
+        let kind = match parts[7] {
+            "host" => CandidateType::Host,
+            "srflx" => CandidateType::ServerReflexive,
+            "prflx" => CandidateType::PeerReflexive,
+            "relay" => CandidateType::Relay,
+            _ => return Err(bad()),
+        };
+
+        Ok(Self {
+            foundation: parts[0].to_string(),
+            component: parts[1].parse().map_err(|_| bad())?,
+            transport: parts[2].to_ascii_lowercase(),
+            priority: parts[3].parse().map_err(|_| bad())?,
+            address: SocketAddr::new(ip, port),
+            kind,
+        })
+    }
+
+    /// SDP attribute form (without `a=`)
+    pub fn to_sdp(&self) -> String {
+        format!(
+            "candidate:{} {} {} {} {} {} typ {}",
+            self.foundation, self.component, self.transport, self.priority,
+            self.address.ip(), self.address.port(), self.kind.as_str(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceState {
+    New,
+    Checking,
+    Connected,
+    Disconnected,
+}
+
+/// ICE-lite agent. The browser runs full ICE as the controlling agent; we
+/// answer its checks and use the pair it nominates.
+#[derive(Debug)]
+pub struct IceLiteAgent {
+    local_ufrag: String,
+    local_pwd: String,
+    remote_ufrag: String,
+    state: IceState,
+    selected: Option<SocketAddr>,
+    last_check: Option<Instant>,
+}
+
+impl IceLiteAgent {
+    pub fn new(stream: &NegotiatedStream) -> Self {
+        Self {
+            local_ufrag: stream.local_ice_ufrag.clone(),
+            local_pwd: stream.local_ice_pwd.clone(),
+            remote_ufrag: stream.remote_ice_ufrag.clone(),
+            state: IceState::New,
+            selected: None,
+            last_check: None,
+        }
+    }
+
+    pub fn state(&self) -> IceState {
+        self.state
+    }
+
+    /// Address of the nominated pair
+    pub fn selected(&self) -> Option<SocketAddr> {
+        self.selected
+    }
+
+    /// Handle a STUN packet; returns the binding response to send back
+    pub fn handle_stun(&mut self, from: SocketAddr, packet: &[u8], now: Instant) -> Result<Vec<u8>, WebRtcError> {
+        let request = stun::parse_binding_request(packet, self.local_pwd.as_bytes())?;
+
+        let expected = format!("{}:{}", self.local_ufrag, self.remote_ufrag);
+        if request.username.as_deref() != Some(expected.as_str()) {
+            return Err(WebRtcError::IceAuthFailed);
+        }
+
+        self.last_check = Some(now);
+        if request.use_candidate {
+            if self.selected != Some(from) {
+                tracing::debug!(%from, "ICE pair nominated");
+            }
+            self.selected = Some(from);
+            self.state = IceState::Connected;
+        } else if self.state == IceState::New {
+            self.state = IceState::Checking;
+        }
+
+        Ok(stun::binding_success(&request.transaction_id, from, self.local_pwd.as_bytes()))
+    }
+
+    /// Consent freshness: the browser re-checks every few seconds
+    pub fn check_consent(&mut self, now: Instant) -> IceState {
+        if self.state == IceState::Connected
+            && self.last_check.is_some_and(|t| now.duration_since(t) > CONSENT_TIMEOUT)
+        {
+            self.state = IceState::Disconnected;
+        }
+        self.state
+    }
+}
+
+/// STUN binding (RFC 8489) with short-term credentials
+pub mod stun {
+    use super::*;
+
+    const MAGIC_COOKIE: u32 = 0x2112_A442;
+    const BINDING_REQUEST: u16 = 0x0001;
+    const BINDING_SUCCESS: u16 = 0x0101;
+    const ATTR_USERNAME: u16 = 0x0006;
+    const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
+    const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+    const ATTR_USE_CANDIDATE: u16 = 0x0025;
+    const ATTR_FINGERPRINT: u16 = 0x8028;
+    const FINGERPRINT_XOR: u32 = 0x5354_554e;
+
+    /// Authenticated binding request
+    #[derive(Debug, Clone)]
+    pub struct BindingRequest {
+        pub transaction_id: [u8; 12],
+        pub username: Option<String>,
+        pub use_candidate: bool,
+    }
+
+    /// Whether a datagram looks like STUN (RFC 7983 demux + magic cookie)
+    pub fn is_stun(packet: &[u8]) -> bool {
+        packet.len() >= 20
+            && packet[0] < 4
+            && u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]) == MAGIC_COOKIE
+    }
+
+    /// Parse a binding request and verify MESSAGE-INTEGRITY with `key`
+    pub fn parse_binding_request(packet: &[u8], key: &[u8]) -> Result<BindingRequest, WebRtcError> {
+        if !is_stun(packet) {
+            return Err(WebRtcError::InvalidStun("not a STUN message".to_string()));
+        }
+        let msg_type = u16::from_be_bytes([packet[0], packet[1]]);
+        if msg_type != BINDING_REQUEST {
+            return Err(WebRtcError::InvalidStun(format!("unexpected type {:#06x}", msg_type)));
+        }
+        let length = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+        if packet.len() < 20 + length {
+            return Err(WebRtcError::InvalidStun("truncated".to_string()));
+        }
+
+        let mut transaction_id = [0u8; 12];
+        transaction_id.copy_from_slice(&packet[8..20]);
+
+        let mut username = None;
+        let mut use_candidate = false;
+        let mut integrity_ok = false;
+        let mut offset = 20;
+
+        while offset + 4 <= 20 + length {
+            let attr_type = u16::from_be_bytes([packet[offset], packet[offset + 1]]);
+            let attr_len = u16::from_be_bytes([packet[offset + 2], packet[offset + 3]]) as usize;
+            let value_start = offset + 4;
+            let value_end = value_start + attr_len;
+            if value_end > 20 + length {
+                return Err(WebRtcError::InvalidStun("attribute overruns message".to_string()));
+            }
+            let value = &packet[value_start..value_end];
+
+            match attr_type {
+                ATTR_USERNAME => username = Some(String::from_utf8_lossy(value).into_owned()),
+                ATTR_USE_CANDIDATE => use_candidate = true,
+                ATTR_MESSAGE_INTEGRITY => {
+                    if attr_len != 20 {
+                        return Err(WebRtcError::InvalidStun("bad MESSAGE-INTEGRITY".to_string()));
+                    }
+                    let expected = hmac_sha1(key, &with_length(&packet[..offset], value_end - 20));
+                    integrity_ok = constant_time_eq(&expected, value);
+                }
+                ATTR_FINGERPRINT => {
+                    let expected = crc32fast::hash(&with_length(&packet[..offset], value_end - 20)) ^ FINGERPRINT_XOR;
+                    if value != expected.to_be_bytes() {
+                        return Err(WebRtcError::InvalidStun("bad FINGERPRINT".to_string()));
+                    }
+                }
+                _ => {}
+            }
+
+            // Attributes after MESSAGE-INTEGRITY (other than FINGERPRINT) are ignored
+            if attr_type == ATTR_MESSAGE_INTEGRITY && !integrity_ok {
+                break;
+            }
+            offset = value_end + (4 - attr_len % 4) % 4;
+        }
+
+        if !integrity_ok {
+            return Err(WebRtcError::IceAuthFailed);
+        }
+
+        Ok(BindingRequest { transaction_id, username, use_candidate })
+    }
+
+    /// Build a binding success response with XOR-MAPPED-ADDRESS, signed with `key`
+    pub fn binding_success(transaction_id: &[u8; 12], mapped: SocketAddr, key: &[u8]) -> Vec<u8> {
+        build(BINDING_SUCCESS, transaction_id, &[(ATTR_XOR_MAPPED_ADDRESS, xor_address(mapped, transaction_id))], key)
+    }
+
+    /// Build a binding request (used by tests and diagnostics)
+    pub fn binding_request(transaction_id: &[u8; 12], username: &str, use_candidate: bool, key: &[u8]) -> Vec<u8> {
+        let mut attrs = vec![(ATTR_USERNAME, username.as_bytes().to_vec())];
+        if use_candidate {
+            attrs.push((ATTR_USE_CANDIDATE, Vec::new()));
+        }
+        build(BINDING_REQUEST, transaction_id, &attrs, key)
+    }
+
+    /// Decode XOR-MAPPED-ADDRESS from a response
+    pub fn mapped_address(packet: &[u8]) -> Option<SocketAddr> {
+        let mut offset = 20;
+        while offset + 4 <= packet.len() {
+            let attr_type = u16::from_be_bytes([packet[offset], packet[offset + 1]]);
+            let attr_len = u16::from_be_bytes([packet[offset + 2], packet[offset + 3]]) as usize;
+            let value = packet.get(offset + 4..offset + 4 + attr_len)?;
+            if attr_type == ATTR_XOR_MAPPED_ADDRESS && attr_len >= 8 {
+                let port = u16::from_be_bytes([value[2], value[3]]) ^ (MAGIC_COOKIE >> 16) as u16;
+                let cookie = MAGIC_COOKIE.to_be_bytes();
+                return match value[1] {
+                    0x01 => {
+                        let ip: [u8; 4] = std::array::from_fn(|i| value[4 + i] ^ cookie[i]);
+                        Some(SocketAddr::new(IpAddr::from(ip), port))
+                    }
+                    0x02 if attr_len >= 20 => {
+                        let mask: Vec<u8> = cookie.iter().chain(&packet[8..20]).copied().collect();
+                        let ip: [u8; 16] = std::array::from_fn(|i| value[4 + i] ^ mask[i]);
+                        Some(SocketAddr::new(IpAddr::from(ip), port))
+                    }
+                    _ => None,
+                };
+            }
+            offset += 4 + attr_len + (4 - attr_len % 4) % 4;
+        }
+        None
+    }
+
+    fn build(msg_type: u16, transaction_id: &[u8; 12], attrs: &[(u16, Vec<u8>)], key: &[u8]) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(96);
+        msg.extend_from_slice(&msg_type.to_be_bytes());
+        msg.extend_from_slice(&[0, 0]);
+        msg.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        msg.extend_from_slice(transaction_id);
+
+        for (attr_type, value) in attrs {
+            push_attr(&mut msg, *attr_type, value);
+        }
+
+        let mac = hmac_sha1(key, &with_length(&msg, msg.len() - 20 + 24));
+        push_attr(&mut msg, ATTR_MESSAGE_INTEGRITY, &mac);
+
+        let crc = crc32fast::hash(&with_length(&msg, msg.len() - 20 + 8)) ^ FINGERPRINT_XOR;
+        push_attr(&mut msg, ATTR_FINGERPRINT, &crc.to_be_bytes());
+
+        let length = (msg.len() - 20) as u16;
+        msg[2..4].copy_from_slice(&length.to_be_bytes());
+        msg
+    }
+
+    fn push_attr(msg: &mut Vec<u8>, attr_type: u16, value: &[u8]) {
+        msg.extend_from_slice(&attr_type.to_be_bytes());
+        msg.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        msg.extend_from_slice(value);
+        msg.resize(msg.len() + (4 - value.len() % 4) % 4, 0);
+    }
+
+    fn xor_address(addr: SocketAddr, transaction_id: &[u8; 12]) -> Vec<u8> {
+        let cookie = MAGIC_COOKIE.to_be_bytes();
+        let port = addr.port() ^ (MAGIC_COOKIE >> 16) as u16;
+        let mut value = vec![0];
+        match addr.ip() {
+            IpAddr::V4(ip) => {
+                value.push(0x01);
+                value.extend_from_slice(&port.to_be_bytes());
+                value.extend(ip.octets().iter().zip(cookie).map(|(b, c)| b ^ c));
+            }
+            IpAddr::V6(ip) => {
+                value.push(0x02);
+                value.extend_from_slice(&port.to_be_bytes());
+                let mask = cookie.iter().chain(transaction_id.iter());
+                value.extend(ip.octets().iter().zip(mask).map(|(b, m)| b ^ m));
+            }
+        }
+        value
+    }
+
+    /// Copy of `prefix` with the header length field rewritten
+    fn with_length(prefix: &[u8], length: usize) -> Vec<u8> {
+        let mut buf = prefix.to_vec();
+        buf[2..4].copy_from_slice(&(length as u16).to_be_bytes());
+        buf
+    }
+
+    fn hmac_sha1(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
+
+// =============================================================================
+// DTLS-SRTP
+// =============================================================================
+
+/// SRTP protection profile negotiated via the DTLS use_srtp extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrtpProfile {
+    Aes128CmHmacSha1_80,
+    AeadAes128Gcm,
+}
+
+/// Sans-IO DTLS-SRTP engine supplied by the media plane.
+///
+/// The engine owns the DTLS state machine and SRTP contexts; the peer feeds
+/// it DTLS datagrams and checks the certificate it reports against the
+/// fingerprint signalled in SDP before any media flows.
+pub trait DtlsSrtp: Send {
+    /// Start the handshake; returns datagrams to send (empty for the server)
+    fn start(&mut self, role: DtlsRole) -> Result<Vec<Vec<u8>>, WebRtcError>;
+    /// Feed an incoming DTLS datagram; returns datagrams to send
+    fn handle_dtls(&mut self, datagram: &[u8]) -> Result<Vec<Vec<u8>>, WebRtcError>;
+    /// SHA-256 fingerprint of the certificate the peer presented
+    fn peer_fingerprint(&self) -> Option<Fingerprint>;
+    /// Negotiated profile once the handshake has completed
+    fn srtp_profile(&self) -> Option<SrtpProfile>;
+    /// Encrypt and authenticate an RTP packet
+    fn protect_rtp(&mut self, packet: &[u8]) -> Result<Vec<u8>, WebRtcError>;
+    /// Verify and decrypt an SRTCP packet
+    fn unprotect_rtcp(&mut self, packet: &[u8]) -> Result<Vec<u8>, WebRtcError>;
+}
+
+// =============================================================================
+// RTP
+// =============================================================================
+
+/// RTP payloader for negotiated video codec
+#[derive(Debug)]
+pub struct RtpPayloader {
+    codec: VideoCodec,
+    payload_type: u8,
+    ssrc: u32,
+    sequence: u16,
+    mtu: usize,
+}
+
+impl RtpPayloader {
+    pub fn new(codec: VideoCodec, payload_type: u8, ssrc: u32) -> Self {
+        Self {
+            codec,
+            payload_type,
+            ssrc,
+            sequence: random_u32() as u16,
+            mtu: 1200,
+        }
+    }
+
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Payload one encoded frame; the last packet carries the marker bit
+    pub fn payload(&mut self, frame: &VideoFrame) -> Vec<RtpPacket> {
+        let max = self.mtu.saturating_sub(12 + SRTP_OVERHEAD).max(16);
+        let payloads = match self.codec {
+            VideoCodec::H264 => h264_payloads(&frame.data, max),
+            _ => vp9_payloads(&frame.data, frame.keyframe, max),
+        };
+        // 90 kHz video clock
+        let timestamp = (frame.timestamp.wrapping_mul(90)) as u32;
+        let count = payloads.len();
+
+        payloads.into_iter()
+            .enumerate()
+            .map(|(i, payload)| {
+                let packet = RtpPacket {
+                    version: 2,
+                    padding: false,
+                    extension: false,
+                    csrc_count: 0,
+                    marker: i + 1 == count,
+                    payload_type: self.payload_type,
+                    sequence: self.sequence,
+                    timestamp,
+                    ssrc: self.ssrc,
+                    payload,
+                };
+                self.sequence = self.sequence.wrapping_add(1);
+                packet
+            })
+            .collect()
+    }
+}
+
+/// RFC 6184: single NAL unit packets, FU-A for NALs over `max`
+fn h264_payloads(data: &[u8], max: usize) -> Vec<Vec<u8>> {
+    let mut payloads = Vec::new();
+
+    for nal in split_annexb(data) {
+        if nal.len() <= max {
+            payloads.push(nal.to_vec());
+            continue;
+        }
+
+        let indicator = (nal[0] & 0xE0) | 28;
+        let nal_type = nal[0] & 0x1F;
+        let chunks: Vec<_> = nal[1..].chunks(max - 2).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let mut header = nal_type;
+            if i == 0 {
+                header |= 0x80;
+            }
+            if i + 1 == chunks.len() {
+                header |= 0x40;
+            }
+            let mut payload = Vec::with_capacity(chunk.len() + 2);
+            payload.push(indicator);
+            payload.push(header);
+            payload.extend_from_slice(chunk);
+            payloads.push(payload);
+        }
+    }
+
+    payloads
+}
+
+/// Split an Annex-B byte stream on 3- and 4-byte start codes
+fn split_annexb(data: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            if let Some(s) = start {
+                let end = if i > s && data[i - 1] == 0 { i - 1 } else { i };
+                nals.push(&data[s..end]);
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    match start {
+        Some(s) => nals.push(&data[s..]),
+        None => nals.push(data),
+    }
+    nals.retain(|n| !n.is_empty());
+    nals
+}
+
+/// VP9 RTP payload descriptor (flexible mode off, no layer indices)
+fn vp9_payloads(data: &[u8], keyframe: bool, max: usize) -> Vec<Vec<u8>> {
+    let chunks: Vec<_> = data.chunks(max - 1).collect();
+    chunks.iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut descriptor = 0u8;
+            if !keyframe {
+                descriptor |= 0x40; // P: inter-picture predicted
+            }
+            if i == 0 {
+                descriptor |= 0x08; // B: start of frame
+            }
+            if i + 1 == chunks.len() {
+                descriptor |= 0x04; // E: end of frame
+            }
+            let mut payload = Vec::with_capacity(chunk.len() + 1);
+            payload.push(descriptor);
+            payload.extend_from_slice(chunk);
+            payload
+        })
+        .collect()
+}
+
+/// Feedback carried in RTCP from the client
+#[derive(Debug, Clone, PartialEq)]
+pub enum RtcpFeedback {
+    /// Receiver report: fraction of packets lost since the last report
+    ReceiverReport { fraction_lost: f64, jitter: u32 },
+    /// Generic NACK (RFC 4585)
+    Nack { lost: Vec<u16> },
+    /// PLI or FIR
+    KeyframeRequest,
+    /// Receiver estimated max bitrate
+    Remb { bitrate_bps: u64 },
+}
+
+/// Parse a (decrypted) compound RTCP packet
+pub fn parse_rtcp(buf: &[u8]) -> Vec<RtcpFeedback> {
+    let mut feedback = Vec::new();
+    let mut offset = 0;
+
+    while offset + 4 <= buf.len() {
+        let header = &buf[offset..];
+        if header[0] >> 6 != 2 {
+            break;
+        }
+        let count = header[0] & 0x1F;
+        let packet_type = header[1];
+        let length = (u16::from_be_bytes([header[2], header[3]]) as usize + 1) * 4;
+        let Some(packet) = buf.get(offset..offset + length) else { break };
+
+        match packet_type {
+            // SR (report blocks after 20 bytes of sender info) / RR
+            200 | 201 if count > 0 => {
+                let block = if packet_type == 200 { 28 } else { 8 };
+                if packet.len() >= block + 24 {
+                    feedback.push(RtcpFeedback::ReceiverReport {
+                        fraction_lost: packet[block + 4] as f64 / 256.0,
+                        jitter: u32::from_be_bytes([
+                            packet[block + 12], packet[block + 13], packet[block + 14], packet[block + 15],
+                        ]),
+                    });
+                }
+            }
+            205 if count == 1 => {
+                let mut lost = Vec::new();
+                for fci in packet.get(12..).unwrap_or_default().chunks_exact(4) {
+                    let pid = u16::from_be_bytes([fci[0], fci[1]]);
+                    let blp = u16::from_be_bytes([fci[2], fci[3]]);
+                    lost.push(pid);
+                    lost.extend((0..16).filter(|b| blp & (1 << b) != 0).map(|b| pid.wrapping_add(b + 1)));
+                }
+                feedback.push(RtcpFeedback::Nack { lost });
+            }
+            206 if count == 1 || count == 4 => feedback.push(RtcpFeedback::KeyframeRequest),
+            206 if count == 15 && packet.len() >= 20 && &packet[12..16] == b"REMB" => {
+                let exp = packet[17] >> 2;
+                let mantissa = ((packet[17] as u64 & 0x03) << 16)
+                    | ((packet[18] as u64) << 8)
+                    | packet[19] as u64;
+                feedback.push(RtcpFeedback::Remb { bitrate_bps: mantissa << exp });
+            }
+            _ => {}
+        }
+
+        offset += length;
+    }
+
+    feedback
+}
+
+// =============================================================================
+// Peer
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerState {
+    /// Waiting for a nominated ICE pair
+    Connecting,
+    /// DTLS handshake in progress
+    Handshaking,
+    /// SRTP keyed and fingerprint verified
+    Connected,
+    Failed,
+}
+
+/// Output of feeding a datagram to the peer
+#[derive(Debug, Default)]
+pub struct PeerOutput {
+    pub datagrams: Vec<(SocketAddr, Vec<u8>)>,
+    pub events: Vec<TransportEvent>,
+}
+
+/// Sans-IO WebRTC peer for one pixel stream
+pub struct WebRtcPeer {
+    stream: NegotiatedStream,
+    ice: IceLiteAgent,
+    dtls: Box<dyn DtlsSrtp>,
+    payloader: RtpPayloader,
+    state: PeerState,
+    /// Plain RTP of recently sent packets, for NACK
+    history: VecDeque<(u16, Vec<u8>)>,
+}
+
+impl WebRtcPeer {
+    pub fn new(stream: NegotiatedStream, dtls: Box<dyn DtlsSrtp>) -> Self {
+        Self {
+            ice: IceLiteAgent::new(&stream),
+            payloader: RtpPayloader::new(stream.codec, stream.payload_type, stream.ssrc),
+            stream,
+            dtls,
+            state: PeerState::Connecting,
+            history: VecDeque::with_capacity(RTX_HISTORY),
+        }
+    }
+
+    pub fn state(&self) -> PeerState {
+        self.state
+    }
+
+    pub fn codec(&self) -> VideoCodec {
+        self.stream.codec
+    }
+
+    /// Demultiplex an incoming datagram (RFC 7983)
+    pub fn handle_datagram(&mut self, from: SocketAddr, data: &[u8], now: Instant) -> Result<PeerOutput, WebRtcError> {
+        let mut out = PeerOutput::default();
+        let Some(&first) = data.first() else { return Ok(out) };
+
+        match first {
+            0..=3 => {
+                let response = self.ice.handle_stun(from, data, now)?;
+                out.datagrams.push((from, response));
+
+                if self.state == PeerState::Connecting && self.ice.state() == IceState::Connected {
+                    self.state = PeerState::Handshaking;
+                    for datagram in self.dtls.start(self.stream.dtls_role)? {
+                        out.datagrams.push((from, datagram));
+                    }
+                }
+            }
+            20..=63 => {
+                if self.ice.selected() != Some(from) {
+                    return Ok(out);
+                }
+                let replies = self.dtls.handle_dtls(data).inspect_err(|_| self.state = PeerState::Failed)?;
+                out.datagrams.extend(replies.into_iter().map(|d| (from, d)));
+
+                if self.state == PeerState::Handshaking && self.dtls.srtp_profile().is_some() {
+                    if self.dtls.peer_fingerprint().as_ref() != Some(&self.stream.remote_fingerprint) {
+                        self.state = PeerState::Failed;
+                        return Err(WebRtcError::FingerprintMismatch);
+                    }
+                    self.state = PeerState::Connected;
+                    out.events.push(TransportEvent::Connected(TransportKind::WebRtc));
+                    // Start the client decoder on a clean frame
+                    out.events.push(TransportEvent::KeyframeRequested);
+                }
+            }
+            // rtcp-mux: RTCP packet types occupy 192..=223 in the second byte
+            128..=191 if self.state == PeerState::Connected
+                && self.ice.selected() == Some(from)
+                && data.get(1).is_some_and(|pt| (192..=223).contains(pt)) =>
+            {
+                let rtcp = self.dtls.unprotect_rtcp(data)?;
+                for feedback in parse_rtcp(&rtcp) {
+                    self.apply_feedback(from, feedback, &mut out)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(out)
+    }
+
+    /// Payload, protect and address a video frame
+    pub fn send_video(&mut self, frame: &VideoFrame) -> Result<Vec<(SocketAddr, Vec<u8>)>, WebRtcError> {
+        let to = match (self.state, self.ice.selected()) {
+            (PeerState::Connected, Some(addr)) => addr,
+            _ => return Err(WebRtcError::NotConnected),
+        };
+
+        let mut datagrams = Vec::new();
+        for packet in self.payloader.payload(frame) {
+            let plain = packet.to_bytes();
+            if self.stream.nack {
+                if self.history.len() == RTX_HISTORY {
+                    self.history.pop_front();
+                }
+                self.history.push_back((packet.sequence, plain.clone()));
+            }
+            datagrams.push((to, self.dtls.protect_rtp(&plain)?));
+        }
+        Ok(datagrams)
+    }
+
+    /// Consent freshness check; returns false once the path is gone
+    pub fn check_consent(&mut self, now: Instant) -> bool {
+        self.ice.check_consent(now) != IceState::Disconnected
+    }
+
+    fn apply_feedback(&mut self, from: SocketAddr, feedback: RtcpFeedback, out: &mut PeerOutput) -> Result<(), WebRtcError> {
+        match feedback {
+            RtcpFeedback::ReceiverReport { fraction_lost, .. } => {
+                out.events.push(TransportEvent::PacketLoss(fraction_lost));
+            }
+            RtcpFeedback::Remb { bitrate_bps } => {
+                out.events.push(TransportEvent::BandwidthEstimate((bitrate_bps / 1000) as u32));
+            }
+            RtcpFeedback::KeyframeRequest => out.events.push(TransportEvent::KeyframeRequested),
+            RtcpFeedback::Nack { lost } => {
+                for seq in lost {
+                    let Some((_, plain)) = self.history.iter().find(|(s, _)| *s == seq) else { continue };
+                    let protected = self.dtls.protect_rtp(plain)?;
+                    out.datagrams.push((from, protected));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn random_u32() -> u32 {
+    let bytes = uuid::Uuid::new_v4().into_bytes();
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebRtcError {
+    InvalidSdp(String),
+    NoVideoMedia,
+    InsecureProfile(String),
+    MissingIceCredentials,
+    MissingFingerprint,
+    UnsupportedCodec,
+    InvalidStun(String),
+    IceAuthFailed,
+    FingerprintMismatch,
+    NotConnected,
+    Dtls(String),
+    Srtp(String),
+}
+
+impl std::fmt::Display for WebRtcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidSdp(e) => write!(f, "Invalid SDP: {}", e),
+            Self::NoVideoMedia => write!(f, "Offer has no video section"),
+            Self::InsecureProfile(p) => write!(f, "Media profile {} is not DTLS-SRTP", p),
+            Self::MissingIceCredentials => write!(f, "Offer has no ICE credentials"),
+            Self::MissingFingerprint => write!(f, "Offer has no sha-256 certificate fingerprint"),
+            Self::UnsupportedCodec => write!(f, "No supported video codec offered"),
+            Self::InvalidStun(e) => write!(f, "Invalid STUN message: {}", e),
+            Self::IceAuthFailed => write!(f, "ICE connectivity check failed authentication"),
+            Self::FingerprintMismatch => write!(f, "DTLS certificate does not match signalled fingerprint"),
+            Self::NotConnected => write!(f, "Peer not connected"),
+            Self::Dtls(e) => write!(f, "DTLS error: {}", e),
+            Self::Srtp(e) => write!(f, "SRTP error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebRtcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFER: &str = "v=0\r\n\
+o=- 4611731400430051336 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+a=mid:0\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 102 98\r\n\
+a=mid:1\r\n\
+a=ice-ufrag:rem1\r\n\
+a=ice-pwd:remotepassword0123456789\r\n\
+a=fingerprint:sha-256 AB:CD:EF\r\n\
+a=setup:actpass\r\n\
+a=recvonly\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f\r\n\
+a=rtcp-fb:102 nack\r\n\
+a=rtcp-fb:102 nack pli\r\n\
+a=rtpmap:98 VP9/90000\r\n\
+a=candidate:842163049 1 udp 1677729535 198.51.100.7 50000 typ srflx raddr 0.0.0.0 rport 0\r\n";
+
+    fn local() -> LocalEndpoint {
+        LocalEndpoint::new(Fingerprint::new("sha-256", "11:22:33"), &["192.0.2.1:3478".parse().unwrap()])
+    }
+
+    #[test]
+    fn test_negotiate_answer() {
+        let local = local();
+        let (answer, stream) = negotiate(OFFER, &local, VideoCodec::H264).unwrap();
+
+        assert_eq!(stream.codec, VideoCodec::H264);
+        assert_eq!(stream.payload_type, 102);
+        assert_eq!(stream.mid, "1");
+        assert_eq!(stream.dtls_role, DtlsRole::Server);
+        assert!(stream.nack);
+        assert_eq!(stream.remote_candidates.len(), 1);
+
+        assert!(answer.contains("m=audio 0 UDP/TLS/RTP/SAVPF 111"));
+        assert!(answer.contains("m=video 9 UDP/TLS/RTP/SAVPF 102"));
+        assert!(answer.contains("a=setup:passive"));
+        assert!(answer.contains("a=sendonly"));
+        assert!(answer.contains(&format!("a=ice-ufrag:{}", local.ice_ufrag)));
+
+        // Preferred codec wins when offered; VP8 is never picked
+        let (_, vp9) = negotiate(OFFER, &local, VideoCodec::VP9).unwrap();
+        assert_eq!(vp9.payload_type, 98);
+
+        let plain = OFFER.replace("UDP/TLS/RTP/SAVPF 96", "RTP/AVP 96");
+        assert!(matches!(negotiate(&plain, &local, VideoCodec::H264), Err(WebRtcError::InsecureProfile(_))));
+    }
+
+    #[test]
+    fn test_ice_lite_nomination() {
+        let (_, stream) = negotiate(OFFER, &local(), VideoCodec::H264).unwrap();
+        let mut agent = IceLiteAgent::new(&stream);
+        let from: SocketAddr = "198.51.100.7:50000".parse().unwrap();
+        let username = format!("{}:{}", stream.local_ice_ufrag, stream.remote_ice_ufrag);
+        let key = stream.local_ice_pwd.as_bytes();
+        let now = Instant::now();
+
+        // Wrong password
+        let forged = stun::binding_request(&[1; 12], &username, true, b"wrong");
+        assert_eq!(agent.handle_stun(from, &forged, now), Err(WebRtcError::IceAuthFailed));
+
+        let check = stun::binding_request(&[2; 12], &username, false, key);
+        agent.handle_stun(from, &check, now).unwrap();
+        assert_eq!(agent.state(), IceState::Checking);
+
+        let nominate = stun::binding_request(&[3; 12], &username, true, key);
+        let response = agent.handle_stun(from, &nominate, now).unwrap();
+        assert_eq!(agent.state(), IceState::Connected);
+        assert_eq!(agent.selected(), Some(from));
+        assert_eq!(stun::mapped_address(&response), Some(from));
+
+        assert_eq!(agent.check_consent(now + Duration::from_secs(31)), IceState::Disconnected);
+    }
+
+    #[test]
+    fn test_h264_fragmentation_and_rtcp() {
+        let mut data = vec![0, 0, 0, 1, 0x67, 0x42, 0xc0, 0x1e, 0, 0, 1, 0x65];
+        data.extend(std::iter::repeat_n(0xAB, 3000));
+        let frame = VideoFrame { timestamp: 1000, keyframe: true, codec: VideoCodec::H264, data, width: 1920, height: 1080 };
+
+        let mut payloader = RtpPayloader::new(VideoCodec::H264, 102, 7);
+        let packets = payloader.payload(&frame);
+
+        // SPS as a single NAL, IDR split into FU-A fragments
+        assert_eq!(packets[0].payload, vec![0x67, 0x42, 0xc0, 0x1e]);
+        assert_eq!(packets[1].payload[0] & 0x1F, 28);
+        assert_eq!(packets[1].payload[1], 0x80 | 0x05);
+        assert_eq!(packets.last().unwrap().payload[1], 0x40 | 0x05);
+        assert!(packets.last().unwrap().marker);
+        assert!(packets.iter().all(|p| p.to_bytes().len() <= 1200 - SRTP_OVERHEAD));
+
+        // RR with 25% loss followed by PLI
+        let mut rtcp = vec![0x81, 201, 0, 7, 0, 0, 0, 1, 0, 0, 0, 7, 64, 0, 0, 3];
+        rtcp.extend([0; 16]);
+        rtcp.extend([0x81, 206, 0, 2, 0, 0, 0, 1, 0, 0, 0, 7]);
+        let feedback = parse_rtcp(&rtcp);
+        assert_eq!(feedback[0], RtcpFeedback::ReceiverReport { fraction_lost: 0.25, jitter: 0 });
+        assert_eq!(feedback[1], RtcpFeedback::KeyframeRequest);
+    }
+}
@@ -0,0 +1,412 @@
+//! Notification channels
+//!
+//! Delivers alert notifications to the destinations of a route. Slack and
+//! Teams messages go to incoming webhooks; PagerDuty gets Events API v2
+//! events keyed by the alert id, so acknowledgments and resolutions close
+//! the same incident; email is handed to an SMTP relay, one message per
+//! recipient.
+
+use super::AlertDestination;
+use crate::{SecurityAlert, Severity};
+use sase_common::smtp::SmtpError;
+use std::time::Duration;
+
+pub use sase_common::smtp::SmtpRelay;
+
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationKind {
+    Triggered,
+    /// Not acknowledged within its SLA; `level` counts from 1
+    Escalated { level: usize },
+    Acknowledged { by: String },
+    Resolved,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertNotification {
+    pub kind: NotificationKind,
+    pub alert: SecurityAlert,
+    /// Route that matched the alert
+    pub route: String,
+}
+
+impl AlertNotification {
+    pub fn subject(&self) -> String {
+        let alert = &self.alert;
+        match &self.kind {
+            NotificationKind::Triggered => format!("[{:?}] {}", alert.severity, alert.alert_type),
+            NotificationKind::Escalated { level } => format!(
+                "[{:?}] ESCALATED (level {}): {} not acknowledged",
+                alert.severity, level, alert.alert_type
+            ),
+            NotificationKind::Acknowledged { by } => format!("Acknowledged by {}: {}", by, alert.alert_type),
+            NotificationKind::Resolved => format!("Resolved: {}", alert.alert_type),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        let alert = &self.alert;
+        let mut body = format!(
+            "Alert: {}\nType: {}\nSeverity: {:?}\nStatus: {:?}\nRaised: {}\nRisk score: {:.0}\n",
+            alert.id,
+            alert.alert_type,
+            alert.severity,
+            alert.status,
+            alert.created_at.to_rfc3339(),
+            alert.enrichment.risk_score,
+        );
+        if !alert.mitre_techniques.is_empty() {
+            body.push_str(&format!("ATT&CK: {}\n", alert.mitre_techniques.join(", ")));
+        }
+        if !alert.enrichment.threat_intel.is_empty() {
+            let matches: Vec<String> = alert.enrichment.threat_intel.iter()
+                .map(|m| format!("{} ({})", m.indicator, m.feed))
+                .collect();
+            body.push_str(&format!("Threat intel: {}\n", matches.join(", ")));
+        }
+        if let Some(assignee) = &alert.assigned_to {
+            body.push_str(&format!("Assigned to: {}\n", assignee));
+        }
+        if let Some(case_id) = &alert.case_id {
+            body.push_str(&format!("Case: {}\n", case_id));
+        }
+        body.push_str(&format!("Route: {}\nEvents: {}\n", self.route, alert.events.len()));
+        body
+    }
+
+    fn color(&self) -> &'static str {
+        match (&self.kind, self.alert.severity) {
+            (NotificationKind::Acknowledged { .. }, _) => "#439FE0",
+            (NotificationKind::Resolved, _) => "#2EB67D",
+            (_, Severity::Critical) => "#A30200",
+            (_, Severity::High) => "#E01E5A",
+            (_, Severity::Medium) => "#ECB22E",
+            _ => "#808080",
+        }
+    }
+}
+
+/// Sends notifications to route destinations
+pub struct ChannelSender {
+    client: reqwest::Client,
+    smtp: Option<SmtpRelay>,
+    pagerduty_url: String,
+    timeout: Duration,
+}
+
+impl ChannelSender {
+    pub fn new() -> Self {
+        let timeout = Duration::from_secs(10);
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .unwrap_or_default(),
+            smtp: None,
+            pagerduty_url: PAGERDUTY_EVENTS_URL.to_string(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_smtp(mut self, relay: SmtpRelay) -> Self {
+        self.smtp = Some(relay);
+        self
+    }
+
+    /// Events API endpoint, e.g. for the EU service region
+    pub fn with_pagerduty_url(mut self, url: &str) -> Self {
+        self.pagerduty_url = url.to_string();
+        self
+    }
+
+    pub async fn send(&self, destination: &AlertDestination, notification: &AlertNotification) -> Result<(), NotifyError> {
+        match destination {
+            AlertDestination::Email { recipients } => self.email(recipients, notification).await,
+            AlertDestination::Slack { channel, webhook } => {
+                self.post(webhook, &slack_payload(channel, notification)).await
+            }
+            AlertDestination::Teams { webhook } => self.post(webhook, &teams_payload(notification)).await,
+            AlertDestination::PagerDuty { service_key } => self.pagerduty(service_key, notification).await,
+            AlertDestination::Webhook { url } => self.post(url, &webhook_payload(notification)).await,
+            AlertDestination::Case => {
+                tracing::info!("Alert {} routed to case management", notification.alert.id);
+                Ok(())
+            }
+        }
+    }
+
+    async fn post(&self, url: &str, payload: &serde_json::Value) -> Result<(), NotifyError> {
+        if url.is_empty() {
+            return Err(NotifyError::NotConfigured("webhook URL".to_string()));
+        }
+        let response = self.client
+            .post(url)
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| NotifyError::Transport(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(NotifyError::Rejected(format!("{} returned {}", url, response.status())));
+        }
+        Ok(())
+    }
+
+    async fn pagerduty(&self, routing_key: &str, notification: &AlertNotification) -> Result<(), NotifyError> {
+        if routing_key.is_empty() {
+            return Err(NotifyError::NotConfigured("PagerDuty routing key".to_string()));
+        }
+        let alert = &notification.alert;
+        let event = match &notification.kind {
+            NotificationKind::Triggered | NotificationKind::Escalated { .. } => serde_json::json!({
+                "routing_key": routing_key,
+                "event_action": "trigger",
+                "dedup_key": alert.id,
+                "payload": {
+                    "summary": notification.subject(),
+                    "source": "sase-soc",
+                    "severity": pagerduty_severity(alert.severity),
+                    "timestamp": alert.created_at.to_rfc3339(),
+                    "component": alert.alert_type,
+                    "group": notification.route,
+                    "custom_details": {
+                        "alert_id": alert.id,
+                        "risk_score": alert.enrichment.risk_score,
+                        "mitre_techniques": alert.mitre_techniques,
+                        "events": alert.events.len(),
+                    },
+                },
+            }),
+            NotificationKind::Acknowledged { .. } => serde_json::json!({
+                "routing_key": routing_key,
+                "event_action": "acknowledge",
+                "dedup_key": alert.id,
+            }),
+            NotificationKind::Resolved => serde_json::json!({
+                "routing_key": routing_key,
+                "event_action": "resolve",
+                "dedup_key": alert.id,
+            }),
+        };
+        let response = self.client
+            .post(&self.pagerduty_url)
+            .json(&event)
+            .send()
+            .await
+            .map_err(|e| NotifyError::Transport(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(NotifyError::Rejected(format!("PagerDuty returned {}: {}", status, text)));
+        }
+        Ok(())
+    }
+
+    async fn email(&self, recipients: &[String], notification: &AlertNotification) -> Result<(), NotifyError> {
+        let relay = self.smtp.as_ref().ok_or_else(|| NotifyError::NotConfigured("SMTP relay".to_string()))?;
+        let subject = notification.subject();
+        let body = notification.body();
+
+        let mut failures = Vec::new();
+        for recipient in recipients {
+            match tokio::time::timeout(self.timeout, relay.send(recipient, &subject, &body)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => failures.push(format!("{}: {}", recipient, NotifyError::from(e))),
+                Err(_) => failures.push(format!("{}: timed out", recipient)),
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifyError::Rejected(failures.join("; ")))
+        }
+    }
+}
+
+impl Default for ChannelSender {
+    fn default() -> Self { Self::new() }
+}
+
+fn pagerduty_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "critical",
+        Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low | Severity::Info => "info",
+    }
+}
+
+fn slack_payload(channel: &str, notification: &AlertNotification) -> serde_json::Value {
+    let mut payload = serde_json::json!({
+        "text": notification.subject(),
+        "attachments": [{
+            "color": notification.color(),
+            "title": notification.subject(),
+            "text": notification.body(),
+            "ts": notification.alert.created_at.timestamp(),
+        }],
+    });
+    if !channel.is_empty() {
+        payload["channel"] = serde_json::json!(channel);
+    }
+    payload
+}
+
+fn teams_payload(notification: &AlertNotification) -> serde_json::Value {
+    let alert = &notification.alert;
+    serde_json::json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": notification.subject(),
+        "themeColor": notification.color().trim_start_matches('#'),
+        "title": notification.subject(),
+        "sections": [{
+            "facts": [
+                { "name": "Alert", "value": alert.id },
+                { "name": "Severity", "value": format!("{:?}", alert.severity) },
+                { "name": "Status", "value": format!("{:?}", alert.status) },
+                { "name": "Raised", "value": alert.created_at.to_rfc3339() },
+                { "name": "ATT&CK", "value": alert.mitre_techniques.join(", ") },
+            ],
+            "text": notification.body(),
+        }],
+    })
+}
+
+fn webhook_payload(notification: &AlertNotification) -> serde_json::Value {
+    let kind = match &notification.kind {
+        NotificationKind::Triggered => serde_json::json!({ "type": "triggered" }),
+        NotificationKind::Escalated { level } => serde_json::json!({ "type": "escalated", "level": level }),
+        NotificationKind::Acknowledged { by } => serde_json::json!({ "type": "acknowledged", "by": by }),
+        NotificationKind::Resolved => serde_json::json!({ "type": "resolved" }),
+    };
+    serde_json::json!({
+        "notification": kind,
+        "route": notification.route,
+        "alert": notification.alert,
+    })
+}
+
+#[derive(Debug)]
+pub enum NotifyError {
+    NotConfigured(String),
+    Transport(String),
+    Rejected(String),
+}
+
+impl std::fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotConfigured(what) => write!(f, "Notification channel not configured: no {}", what),
+            Self::Transport(e) => write!(f, "Notification transport error: {}", e),
+            Self::Rejected(e) => write!(f, "Notification rejected: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for NotifyError {}
+
+impl From<SmtpError> for NotifyError {
+    fn from(e: SmtpError) -> Self {
+        match e {
+            SmtpError::Transport(e) => Self::Transport(e),
+            SmtpError::Rejected(e) => Self::Rejected(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertEnrichment, AlertStatus, ThreatIntelMatch};
+
+    fn notification(kind: NotificationKind) -> AlertNotification {
+        let now = chrono::Utc::now();
+        AlertNotification {
+            kind,
+            alert: SecurityAlert {
+                id: "alert-1".to_string(),
+                events: vec!["e1".to_string(), "e2".to_string()],
+                alert_type: "BruteForce".to_string(),
+                severity: Severity::High,
+                status: AlertStatus::New,
+                created_at: now,
+                updated_at: now,
+                assigned_to: None,
+                mitre_tactics: vec!["TA0006".to_string()],
+                mitre_techniques: vec!["T1110".to_string()],
+                enrichment: AlertEnrichment {
+                    threat_intel: vec![ThreatIntelMatch {
+                        feed: "abuse.ch".to_string(),
+                        indicator: "203.0.113.7".to_string(),
+                        threat_type: "botnet".to_string(),
+                        confidence: 0.9,
+                    }],
+                    risk_score: 72.4,
+                    ..Default::default()
+                },
+                case_id: Some("case-9".to_string()),
+            },
+            route: "High Severity".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_subject_and_body() {
+        assert_eq!(notification(NotificationKind::Triggered).subject(), "[High] BruteForce");
+        assert_eq!(
+            notification(NotificationKind::Escalated { level: 2 }).subject(),
+            "[High] ESCALATED (level 2): BruteForce not acknowledged"
+        );
+        assert_eq!(
+            notification(NotificationKind::Acknowledged { by: "alice".to_string() }).subject(),
+            "Acknowledged by alice: BruteForce"
+        );
+
+        let body = notification(NotificationKind::Triggered).body();
+        assert!(body.contains("Risk score: 72\n"));
+        assert!(body.contains("ATT&CK: T1110\n"));
+        assert!(body.contains("Threat intel: 203.0.113.7 (abuse.ch)\n"));
+        assert!(body.contains("Case: case-9\n"));
+        assert!(!body.contains("Assigned to"));
+        assert!(body.ends_with("Route: High Severity\nEvents: 2\n"));
+    }
+
+    #[test]
+    fn test_payloads() {
+        let triggered = notification(NotificationKind::Triggered);
+        let slack = slack_payload("#soc", &triggered);
+        assert_eq!(slack["channel"], "#soc");
+        assert_eq!(slack["attachments"][0]["color"], "#E01E5A");
+        assert!(slack_payload("", &triggered).get("channel").is_none());
+
+        let resolved = notification(NotificationKind::Resolved);
+        assert_eq!(teams_payload(&resolved)["themeColor"], "2EB67D");
+
+        let escalated = webhook_payload(&notification(NotificationKind::Escalated { level: 3 }));
+        assert_eq!(escalated["notification"], serde_json::json!({ "type": "escalated", "level": 3 }));
+        assert_eq!(escalated["alert"]["id"], "alert-1");
+
+        assert_eq!(pagerduty_severity(Severity::High), "error");
+        assert_eq!(pagerduty_severity(Severity::Info), "info");
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_destinations() {
+        let sender = ChannelSender::new();
+        let triggered = notification(NotificationKind::Triggered);
+
+        for destination in [
+            AlertDestination::Email { recipients: vec!["soc@example.com".to_string()] },
+            AlertDestination::Teams { webhook: String::new() },
+            AlertDestination::PagerDuty { service_key: String::new() },
+        ] {
+            assert!(matches!(
+                sender.send(&destination, &triggered).await,
+                Err(NotifyError::NotConfigured(_))
+            ));
+        }
+        assert!(sender.send(&AlertDestination::Case, &triggered).await.is_ok());
+    }
+}
@@ -0,0 +1,740 @@
+//! Alert Router
+//!
+//! Route alerts to appropriate destinations. Routes match on severity, type
+//! or technique and can be limited to business hours or after hours. Alerts
+//! on a route with an escalation policy move up the chain while nobody
+//! acknowledges them within their SLA; acknowledgments, whether made here
+//! or in PagerDuty, are written back to the alert's status.
+
+pub mod channels;
+pub mod schedule;
+
+pub use channels::{AlertNotification, ChannelSender, NotificationKind, NotifyError, SmtpRelay};
+pub use schedule::{BusinessHours, RouteSchedule};
+
+use crate::{SecurityAlert, Severity, AlertStatus};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+pub struct AlertRouter {
+    routes: dashmap::DashMap<String, AlertRoute>,
+    alert_store: dashmap::DashMap<String, SecurityAlert>,
+    escalation_policies: dashmap::DashMap<String, EscalationPolicy>,
+    /// Delivery and escalation state by alert id
+    tracking: dashmap::DashMap<String, AlertTracking>,
+    sender: ChannelSender,
+    ack_sla: AckSla,
+    stats: AlertStats,
+}
+
+struct AlertStats {
+    total_received: AtomicU64,
+    total_routed: AtomicU64,
+    delivery_failures: AtomicU64,
+    escalations: AtomicU64,
+}
+
+#[derive(Clone)]
+pub struct AlertRoute {
+    pub id: String,
+    pub name: String,
+    pub condition: RouteCondition,
+    pub destinations: Vec<AlertDestination>,
+    pub enabled: bool,
+    /// When the route applies
+    pub schedule: RouteSchedule,
+    /// Escalation policy for alerts left unacknowledged
+    pub escalation: Option<String>,
+}
+
+impl AlertRoute {
+    pub fn new(id: &str, name: &str, condition: RouteCondition) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            condition,
+            destinations: Vec::new(),
+            enabled: true,
+            schedule: RouteSchedule::Always,
+            escalation: None,
+        }
+    }
+
+    pub fn with_destination(mut self, destination: AlertDestination) -> Self {
+        self.destinations.push(destination);
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: RouteSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn with_escalation(mut self, policy_id: &str) -> Self {
+        self.escalation = Some(policy_id.to_string());
+        self
+    }
+}
+
+#[derive(Clone)]
+pub enum RouteCondition {
+    All,
+    /// This severity or higher
+    Severity(Severity),
+    /// Exactly one of these severities
+    Severities(Vec<Severity>),
+    AlertType(String),
+    /// ATT&CK technique id; sub-techniques match their parent
+    Technique(String),
+    Tag(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlertDestination {
+    Email { recipients: Vec<String> },
+    Slack { channel: String, webhook: String },
+    Teams { webhook: String },
+    /// Events API v2 integration (routing) key
+    PagerDuty { service_key: String },
+    Webhook { url: String },
+    Case,
+}
+
+impl AlertDestination {
+    /// Whether acknowledgments and resolutions are sent here too. Email
+    /// only gets the page itself.
+    fn follows_updates(&self) -> bool {
+        !matches!(self, Self::Email { .. } | Self::Case)
+    }
+}
+
+/// Chain of destinations notified in turn while an alert stays
+/// unacknowledged
+#[derive(Clone, Debug)]
+pub struct EscalationPolicy {
+    pub id: String,
+    pub name: String,
+    pub levels: Vec<EscalationLevel>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EscalationLevel {
+    /// Minutes without acknowledgment before this level is notified;
+    /// defaults to the alert's acknowledgment SLA
+    pub after_minutes: Option<i64>,
+    pub destinations: Vec<AlertDestination>,
+    /// Levels outside their schedule are skipped
+    pub schedule: RouteSchedule,
+}
+
+impl EscalationLevel {
+    pub fn new(destinations: Vec<AlertDestination>) -> Self {
+        Self { after_minutes: None, destinations, schedule: RouteSchedule::Always }
+    }
+
+    pub fn after_minutes(mut self, minutes: i64) -> Self {
+        self.after_minutes = Some(minutes);
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: RouteSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+}
+
+/// Minutes an alert may go unacknowledged, per severity
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AckSla {
+    pub critical: i64,
+    pub high: i64,
+    pub medium: i64,
+    pub low: i64,
+    pub info: i64,
+}
+
+impl Default for AckSla {
+    fn default() -> Self {
+        Self {
+            critical: 15,
+            high: 30,
+            medium: 2 * 60,
+            low: 8 * 60,
+            info: 24 * 60,
+        }
+    }
+}
+
+impl AckSla {
+    pub fn minutes(&self, severity: Severity) -> i64 {
+        match severity {
+            Severity::Critical => self.critical,
+            Severity::High => self.high,
+            Severity::Medium => self.medium,
+            Severity::Low => self.low,
+            Severity::Info => self.info,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct AlertTracking {
+    /// Route that matched, named in notifications
+    route: String,
+    /// Destinations paged so far, told about acknowledgment and resolution
+    notified: Vec<AlertDestination>,
+    escalation: Option<EscalationState>,
+}
+
+#[derive(Clone)]
+struct EscalationState {
+    policy_id: String,
+    next_level: usize,
+    due_at: DateTime<Utc>,
+}
+
+/// An alert moved up its escalation chain
+#[derive(Debug, Clone)]
+pub struct Escalation {
+    pub alert_id: String,
+    pub policy_id: String,
+    /// Level notified, counting from 1
+    pub level: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlertRouterStats {
+    pub total_received: u64,
+    pub total_routed: u64,
+    pub delivery_failures: u64,
+    pub escalations: u64,
+}
+
+impl AlertRouter {
+    pub fn new() -> Self {
+        let router = Self {
+            routes: dashmap::DashMap::new(),
+            alert_store: dashmap::DashMap::new(),
+            escalation_policies: dashmap::DashMap::new(),
+            tracking: dashmap::DashMap::new(),
+            sender: ChannelSender::new(),
+            ack_sla: AckSla::default(),
+            stats: AlertStats {
+                total_received: AtomicU64::new(0),
+                total_routed: AtomicU64::new(0),
+                delivery_failures: AtomicU64::new(0),
+                escalations: AtomicU64::new(0),
+            },
+        };
+        router.load_default_routes();
+        router
+    }
+
+    pub fn with_sender(mut self, sender: ChannelSender) -> Self {
+        self.sender = sender;
+        self
+    }
+
+    pub fn with_ack_sla(mut self, ack_sla: AckSla) -> Self {
+        self.ack_sla = ack_sla;
+        self
+    }
+
+    fn load_default_routes(&self) {
+        self.add_route(AlertRoute::new("critical", "Critical Alerts", RouteCondition::Severity(Severity::Critical))
+            .with_destination(AlertDestination::PagerDuty { service_key: "".to_string() })
+            .with_destination(AlertDestination::Case));
+
+        self.add_route(AlertRoute::new("high", "High Severity", RouteCondition::Severity(Severity::High))
+            .with_destination(AlertDestination::Slack { channel: "#security".to_string(), webhook: "".to_string() }));
+    }
+
+    pub async fn route(&self, alert: &SecurityAlert) {
+        self.stats.total_received.fetch_add(1, Ordering::Relaxed);
+        self.alert_store.insert(alert.id.clone(), alert.clone());
+
+        let now = Utc::now();
+        let mut routes: Vec<AlertRoute> = self.routes.iter()
+            .filter(|r| r.enabled && r.schedule.is_active(now) && self.condition_matches(&r.condition, alert))
+            .map(|r| r.clone())
+            .collect();
+        routes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut tracking = AlertTracking::default();
+        for route in &routes {
+            let notification = AlertNotification {
+                kind: NotificationKind::Triggered,
+                alert: alert.clone(),
+                route: route.name.clone(),
+            };
+            for dest in &route.destinations {
+                if tracking.notified.contains(dest) { continue; }
+                self.send_to_destination(dest, &notification).await;
+                tracking.notified.push(dest.clone());
+            }
+            if tracking.route.is_empty() {
+                tracking.route = route.name.clone();
+            }
+
+            // The first matching route with a policy owns escalation
+            if tracking.escalation.is_none() && alert.status == AlertStatus::New {
+                if let Some(policy_id) = &route.escalation {
+                    tracking.escalation = self.start_escalation(policy_id, alert, now);
+                }
+            }
+            self.stats.total_routed.fetch_add(1, Ordering::Relaxed);
+        }
+        self.tracking.insert(alert.id.clone(), tracking);
+    }
+
+    fn start_escalation(&self, policy_id: &str, alert: &SecurityAlert, now: DateTime<Utc>) -> Option<EscalationState> {
+        let Some(policy) = self.escalation_policies.get(policy_id) else {
+            tracing::warn!("Alert {} routed to unknown escalation policy {}", alert.id, policy_id);
+            return None;
+        };
+        let first = policy.levels.first()?;
+        Some(EscalationState {
+            policy_id: policy_id.to_string(),
+            next_level: 0,
+            due_at: now + Duration::minutes(self.level_delay(first, alert.severity)),
+        })
+    }
+
+    fn level_delay(&self, level: &EscalationLevel, severity: Severity) -> i64 {
+        level.after_minutes.unwrap_or_else(|| self.ack_sla.minutes(severity))
+    }
+
+    fn condition_matches(&self, condition: &RouteCondition, alert: &SecurityAlert) -> bool {
+        match condition {
+            RouteCondition::All => true,
+            RouteCondition::Severity(s) => alert.severity >= *s,
+            RouteCondition::Severities(s) => s.contains(&alert.severity),
+            RouteCondition::AlertType(t) => &alert.alert_type == t,
+            RouteCondition::Technique(t) => alert.mitre_techniques.iter()
+                .any(|id| id == t || id.split('.').next() == Some(t.as_str())),
+            RouteCondition::Tag(t) => false, // TODO: check tags
+        }
+    }
+
+    async fn send_to_destination(&self, dest: &AlertDestination, notification: &AlertNotification) {
+        if let Err(e) = self.sender.send(dest, notification).await {
+            self.stats.delivery_failures.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Alert {} notification to {:?} failed: {}", notification.alert.id, dest, e);
+        }
+    }
+
+    pub fn add_route(&self, route: AlertRoute) {
+        self.routes.insert(route.id.clone(), route);
+    }
+
+    pub fn remove_route(&self, route_id: &str) -> Option<AlertRoute> {
+        self.routes.remove(route_id).map(|(_, r)| r)
+    }
+
+    pub fn add_escalation_policy(&self, policy: EscalationPolicy) {
+        self.escalation_policies.insert(policy.id.clone(), policy);
+    }
+
+    pub fn alert(&self, alert_id: &str) -> Option<SecurityAlert> {
+        self.alert_store.get(alert_id).map(|a| a.clone())
+    }
+
+    // =========================================================================
+    // Escalation
+    // =========================================================================
+
+    /// Notify the next level for every alert whose acknowledgment deadline
+    /// has passed. Call periodically, or use [`Self::spawn`].
+    pub async fn escalate_due(&self, now: DateTime<Utc>) -> Vec<Escalation> {
+        let due: Vec<(String, EscalationState)> = self.tracking.iter()
+            .filter_map(|t| {
+                let state = t.escalation.as_ref()?;
+                (state.due_at <= now).then(|| (t.key().clone(), state.clone()))
+            })
+            .collect();
+
+        let mut escalations = Vec::new();
+        for (alert_id, state) in due {
+            if let Some(escalation) = self.escalate(&alert_id, state, now).await {
+                escalations.push(escalation);
+            }
+        }
+        escalations
+    }
+
+    async fn escalate(&self, alert_id: &str, state: EscalationState, now: DateTime<Utc>) -> Option<Escalation> {
+        let alert = self.alert(alert_id)?;
+        let policy = self.escalation_policies.get(&state.policy_id).map(|p| p.clone());
+        let unacknowledged = matches!(alert.status, AlertStatus::New | AlertStatus::Escalated);
+
+        // Skip levels whose schedule is not active right now
+        let level = policy.as_ref().filter(|_| unacknowledged).and_then(|policy| {
+            policy.levels.iter().enumerate()
+                .skip(state.next_level)
+                .find(|(_, level)| level.schedule.is_active(now))
+                .map(|(index, level)| (index, level.clone()))
+        });
+        let Some((index, level)) = level else {
+            if let Some(mut tracking) = self.tracking.get_mut(alert_id) {
+                tracking.escalation = None;
+            }
+            if unacknowledged {
+                tracing::warn!("Alert {} reached the end of escalation policy {} unacknowledged", alert_id, state.policy_id);
+            }
+            return None;
+        };
+
+        let alert = {
+            let mut stored = self.alert_store.get_mut(alert_id)?;
+            stored.status = AlertStatus::Escalated;
+            stored.updated_at = now;
+            stored.clone()
+        };
+        let route = self.tracking.get(alert_id).map(|t| t.route.clone()).unwrap_or_default();
+        let notification = AlertNotification {
+            kind: NotificationKind::Escalated { level: index + 1 },
+            alert: alert.clone(),
+            route,
+        };
+        for dest in &level.destinations {
+            self.send_to_destination(dest, &notification).await;
+        }
+        self.stats.escalations.fetch_add(1, Ordering::Relaxed);
+        tracing::info!("Alert {} escalated to level {} of {}", alert_id, index + 1, state.policy_id);
+
+        let next = policy.as_ref()
+            .and_then(|p| p.levels.get(index + 1))
+            .map(|next| EscalationState {
+                policy_id: state.policy_id.clone(),
+                next_level: index + 1,
+                due_at: now + Duration::minutes(self.level_delay(next, alert.severity)),
+            });
+        if let Some(mut tracking) = self.tracking.get_mut(alert_id) {
+            for dest in &level.destinations {
+                if !tracking.notified.contains(dest) {
+                    tracking.notified.push(dest.clone());
+                }
+            }
+            tracking.escalation = next;
+        }
+
+        Some(Escalation {
+            alert_id: alert_id.to_string(),
+            policy_id: state.policy_id,
+            level: index + 1,
+        })
+    }
+
+    /// Check escalation deadlines every `interval`
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                self.escalate_due(Utc::now()).await;
+            }
+        })
+    }
+
+    // =========================================================================
+    // Acknowledgment
+    // =========================================================================
+
+    /// Acknowledge an alert: it moves to in progress, is assigned to `by`
+    /// if unassigned, and stops escalating. Paged destinations are told.
+    pub async fn acknowledge(&self, alert_id: &str, by: &str) -> Result<SecurityAlert, AlertError> {
+        let alert = {
+            let mut alert = self.alert_store.get_mut(alert_id)
+                .ok_or_else(|| AlertError::NotFound(alert_id.to_string()))?;
+            match alert.status {
+                AlertStatus::New | AlertStatus::Triaging | AlertStatus::Escalated => {}
+                // Already being worked; acknowledging again changes nothing
+                AlertStatus::InProgress => return Ok(alert.clone()),
+                from => return Err(AlertError::InvalidTransition { from, to: AlertStatus::InProgress }),
+            }
+            alert.status = AlertStatus::InProgress;
+            alert.assigned_to.get_or_insert_with(|| by.to_string());
+            alert.updated_at = Utc::now();
+            alert.clone()
+        };
+
+        tracing::info!("Alert {} acknowledged by {}", alert_id, by);
+        self.notify_update(&alert, NotificationKind::Acknowledged { by: by.to_string() }).await;
+        Ok(alert)
+    }
+
+    /// Close an alert as resolved or a false positive
+    pub async fn resolve(&self, alert_id: &str, status: AlertStatus, by: &str) -> Result<SecurityAlert, AlertError> {
+        let alert = {
+            let mut alert = self.alert_store.get_mut(alert_id)
+                .ok_or_else(|| AlertError::NotFound(alert_id.to_string()))?;
+            let from = alert.status;
+            if !matches!(status, AlertStatus::Resolved | AlertStatus::FalsePositive)
+                || matches!(from, AlertStatus::Resolved | AlertStatus::FalsePositive)
+            {
+                return Err(AlertError::InvalidTransition { from, to: status });
+            }
+            alert.status = status;
+            alert.assigned_to.get_or_insert_with(|| by.to_string());
+            alert.updated_at = Utc::now();
+            alert.clone()
+        };
+
+        tracing::info!("Alert {} closed as {:?} by {}", alert_id, status, by);
+        self.notify_update(&alert, NotificationKind::Resolved).await;
+        Ok(alert)
+    }
+
+    /// Stop escalation and tell the destinations already paged
+    async fn notify_update(&self, alert: &SecurityAlert, kind: NotificationKind) {
+        let Some(tracking) = self.tracking.get_mut(&alert.id).map(|mut t| {
+            t.escalation = None;
+            t.clone()
+        }) else {
+            return;
+        };
+        let notification = AlertNotification { kind, alert: alert.clone(), route: tracking.route };
+        for dest in tracking.notified.iter().filter(|d| d.follows_updates()) {
+            self.send_to_destination(dest, &notification).await;
+        }
+    }
+
+    /// Write back an acknowledgment or resolution made in PagerDuty, from a
+    /// V3 webhook payload. Incidents are matched by their incident key,
+    /// which is the alert id sent as the event dedup key. Returns the
+    /// updated alert, or `None` for events that don't change alert status.
+    pub async fn handle_pagerduty_webhook(&self, payload: &serde_json::Value) -> Result<Option<SecurityAlert>, AlertError> {
+        let event = payload.get("event")
+            .ok_or_else(|| AlertError::InvalidWebhook("missing event".to_string()))?;
+        let event_type = event.get("event_type").and_then(|t| t.as_str())
+            .ok_or_else(|| AlertError::InvalidWebhook("missing event_type".to_string()))?;
+        let alert_id = event.pointer("/data/incident_key").and_then(|k| k.as_str())
+            .ok_or_else(|| AlertError::InvalidWebhook("missing data.incident_key".to_string()))?;
+        let by = event.pointer("/agent/summary").and_then(|a| a.as_str())
+            .map(|agent| format!("pagerduty:{}", agent))
+            .unwrap_or_else(|| "pagerduty".to_string());
+
+        match event_type {
+            "incident.acknowledged" => self.acknowledge(alert_id, &by).await.map(Some),
+            "incident.resolved" => {
+                let resolved = self.alert(alert_id)
+                    .is_some_and(|a| matches!(a.status, AlertStatus::Resolved | AlertStatus::FalsePositive));
+                if resolved {
+                    return Ok(None);
+                }
+                self.resolve(alert_id, AlertStatus::Resolved, &by).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn get_open_count(&self) -> u64 {
+        self.alert_store.iter()
+            .filter(|a| matches!(
+                a.status,
+                AlertStatus::New | AlertStatus::Triaging | AlertStatus::InProgress | AlertStatus::Escalated
+            ))
+            .count() as u64
+    }
+
+    pub fn stats(&self) -> AlertRouterStats {
+        AlertRouterStats {
+            total_received: self.stats.total_received.load(Ordering::Relaxed),
+            total_routed: self.stats.total_routed.load(Ordering::Relaxed),
+            delivery_failures: self.stats.delivery_failures.load(Ordering::Relaxed),
+            escalations: self.stats.escalations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for AlertRouter {
+    fn default() -> Self { Self::new() }
+}
+
+#[derive(Debug)]
+pub enum AlertError {
+    NotFound(String),
+    InvalidTransition { from: AlertStatus, to: AlertStatus },
+    InvalidWebhook(String),
+}
+
+impl std::fmt::Display for AlertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "Alert not found: {}", id),
+            Self::InvalidTransition { from, to } => write!(f, "Cannot move alert from {:?} to {:?}", from, to),
+            Self::InvalidWebhook(e) => write!(f, "Invalid webhook payload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AlertError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlertEnrichment;
+
+    fn alert(severity: Severity, techniques: &[&str]) -> SecurityAlert {
+        SecurityAlert {
+            id: uuid::Uuid::new_v4().to_string(),
+            events: vec![],
+            alert_type: "BruteForce".to_string(),
+            severity,
+            status: AlertStatus::New,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            assigned_to: None,
+            mitre_tactics: vec![],
+            mitre_techniques: techniques.iter().map(|t| t.to_string()).collect(),
+            enrichment: AlertEnrichment::default(),
+            case_id: None,
+        }
+    }
+
+    /// Webhook without a URL: every delivery fails locally, so
+    /// `delivery_failures` counts the sends
+    fn hook() -> AlertDestination {
+        AlertDestination::Webhook { url: String::new() }
+    }
+
+    fn router() -> AlertRouter {
+        let router = AlertRouter::new();
+        router.remove_route("critical");
+        router.remove_route("high");
+        router
+    }
+
+    #[tokio::test]
+    async fn test_route_conditions_and_schedules() {
+        let router = router();
+        router.add_route(AlertRoute::new("creds", "Credential Access", RouteCondition::Technique("T1110".to_string()))
+            .with_destination(hook()));
+        router.add_route(AlertRoute::new("medium", "Medium and Up", RouteCondition::Severity(Severity::Medium))
+            .with_destination(AlertDestination::Case));
+        // No working days, so never in business hours
+        let closed = BusinessHours::default().with_days(vec![]);
+        router.add_route(AlertRoute::new("office", "Office Hours", RouteCondition::All)
+            .with_destination(AlertDestination::Case)
+            .with_schedule(RouteSchedule::BusinessHours(closed)));
+
+        // Sub-technique matches its parent; severity is a floor
+        let stuffing = alert(Severity::High, &["T1110.004"]);
+        router.route(&stuffing).await;
+        assert_eq!(router.stats().total_routed, 2);
+        assert_eq!(router.stats().delivery_failures, 1);
+        assert_eq!(router.tracking.get(&stuffing.id).unwrap().route, "Credential Access");
+
+        router.route(&alert(Severity::Low, &["T11100"])).await;
+        assert_eq!(router.stats().total_received, 2);
+        assert_eq!(router.stats().total_routed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_escalation_chain() {
+        let router = router();
+        router.add_escalation_policy(EscalationPolicy {
+            id: "soc".to_string(),
+            name: "SOC".to_string(),
+            levels: vec![
+                EscalationLevel::new(vec![hook()]),
+                EscalationLevel::new(vec![hook()]).after_minutes(10),
+            ],
+        });
+        router.add_route(AlertRoute::new("all", "All", RouteCondition::All)
+            .with_destination(AlertDestination::Case)
+            .with_escalation("soc"));
+
+        let high = alert(Severity::High, &[]);
+        router.route(&high).await;
+        let now = Utc::now();
+
+        // High severity waits its 30 minute acknowledgment SLA
+        assert!(router.escalate_due(now + Duration::minutes(29)).await.is_empty());
+        let first = router.escalate_due(now + Duration::minutes(31)).await;
+        assert_eq!(first.len(), 1);
+        assert_eq!((first[0].policy_id.as_str(), first[0].level), ("soc", 1));
+        assert_eq!(router.alert(&high.id).unwrap().status, AlertStatus::Escalated);
+
+        assert!(router.escalate_due(now + Duration::minutes(40)).await.is_empty());
+        assert_eq!(router.escalate_due(now + Duration::minutes(42)).await[0].level, 2);
+
+        // End of the chain
+        assert!(router.escalate_due(now + Duration::hours(2)).await.is_empty());
+        assert!(router.tracking.get(&high.id).unwrap().escalation.is_none());
+        assert_eq!(router.stats().escalations, 2);
+        assert_eq!(router.get_open_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_acknowledgment_stops_escalation_and_notifies() {
+        let router = router();
+        router.add_escalation_policy(EscalationPolicy {
+            id: "soc".to_string(),
+            name: "SOC".to_string(),
+            levels: vec![EscalationLevel::new(vec![hook()])],
+        });
+        router.add_route(AlertRoute::new("all", "All", RouteCondition::All)
+            .with_destination(AlertDestination::Email { recipients: vec!["soc@example.com".to_string()] })
+            .with_destination(hook())
+            .with_escalation("soc"));
+
+        let critical = alert(Severity::Critical, &[]);
+        router.route(&critical).await;
+        assert_eq!(router.stats().delivery_failures, 2);
+
+        let acked = router.acknowledge(&critical.id, "alice").await.unwrap();
+        assert_eq!(acked.status, AlertStatus::InProgress);
+        assert_eq!(acked.assigned_to.as_deref(), Some("alice"));
+        // Only the webhook follows updates, email got the page alone
+        assert_eq!(router.stats().delivery_failures, 3);
+        assert!(router.escalate_due(Utc::now() + Duration::hours(1)).await.is_empty());
+
+        // Acknowledging again is a no-op that notifies nobody
+        router.acknowledge(&critical.id, "bob").await.unwrap();
+        assert_eq!(router.stats().delivery_failures, 3);
+
+        assert!(matches!(
+            router.resolve(&critical.id, AlertStatus::New, "alice").await,
+            Err(AlertError::InvalidTransition { .. })
+        ));
+        let closed = router.resolve(&critical.id, AlertStatus::FalsePositive, "bob").await.unwrap();
+        assert_eq!(closed.assigned_to.as_deref(), Some("alice"));
+        assert!(matches!(
+            router.acknowledge(&critical.id, "alice").await,
+            Err(AlertError::InvalidTransition { from: AlertStatus::FalsePositive, .. })
+        ));
+        assert!(matches!(router.acknowledge("missing", "alice").await, Err(AlertError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_pagerduty_write_back() {
+        let router = router();
+        let high = alert(Severity::High, &[]);
+        router.route(&high).await;
+
+        let webhook = |event_type: &str| serde_json::json!({
+            "event": {
+                "event_type": event_type,
+                "agent": { "summary": "Dana" },
+                "data": { "incident_key": high.id },
+            }
+        });
+
+        let acked = router.handle_pagerduty_webhook(&webhook("incident.acknowledged")).await.unwrap().unwrap();
+        assert_eq!(acked.assigned_to.as_deref(), Some("pagerduty:Dana"));
+        assert!(router.handle_pagerduty_webhook(&webhook("incident.priority_updated")).await.unwrap().is_none());
+
+        let resolved = router.handle_pagerduty_webhook(&webhook("incident.resolved")).await.unwrap().unwrap();
+        assert_eq!(resolved.status, AlertStatus::Resolved);
+        // Our own resolve echoed back by PagerDuty
+        assert!(router.handle_pagerduty_webhook(&webhook("incident.resolved")).await.unwrap().is_none());
+
+        assert!(matches!(
+            router.handle_pagerduty_webhook(&serde_json::json!({ "event": { "event_type": "incident.resolved" } })).await,
+            Err(AlertError::InvalidWebhook(_))
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+//! Business-hours schedules
+//!
+//! Routes can be limited to, or kept out of, a team's working hours. Hours
+//! are wall-clock times at a fixed UTC offset; holidays count as outside
+//! business hours all day.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// Working days and hours of an on-call team
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BusinessHours {
+    /// Offset of the team's local time from UTC, in minutes
+    pub utc_offset_minutes: i32,
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    /// End of the working day; an end before `start` runs past midnight
+    pub end: NaiveTime,
+    /// Local dates treated as outside business hours
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for BusinessHours {
+    /// Monday to Friday, 09:00-17:00 UTC
+    fn default() -> Self {
+        Self {
+            utc_offset_minutes: 0,
+            days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap_or_default(),
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl BusinessHours {
+    pub fn new(utc_offset_minutes: i32, start: NaiveTime, end: NaiveTime) -> Self {
+        Self { utc_offset_minutes, start, end, ..Self::default() }
+    }
+
+    pub fn with_days(mut self, days: Vec<Weekday>) -> Self {
+        self.days = days;
+        self
+    }
+
+    pub fn with_holiday(mut self, date: NaiveDate) -> Self {
+        self.holidays.push(date);
+        self
+    }
+
+    /// Whether `at` falls within business hours
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let Some(offset) = FixedOffset::east_opt(self.utc_offset_minutes * 60) else {
+            return false;
+        };
+        let local = at.with_timezone(&offset);
+        let time = local.time();
+
+        if self.start <= self.end {
+            time >= self.start && time < self.end && self.is_working_day(local.date_naive())
+        } else if time >= self.start {
+            self.is_working_day(local.date_naive())
+        } else if time < self.end {
+            // Early-morning tail of a shift that began the day before
+            self.is_working_day(local.date_naive() - Duration::days(1))
+        } else {
+            false
+        }
+    }
+
+    fn is_working_day(&self, date: NaiveDate) -> bool {
+        self.days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+}
+
+/// When a route applies
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum RouteSchedule {
+    #[default]
+    Always,
+    /// Only during business hours
+    BusinessHours(BusinessHours),
+    /// Only outside business hours, e.g. for the after-hours on-call
+    AfterHours(BusinessHours),
+}
+
+impl RouteSchedule {
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        match self {
+            Self::Always => true,
+            Self::BusinessHours(hours) => hours.contains(at),
+            Self::AfterHours(hours) => !hours.contains(at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // October 2026: the 19th is a Monday
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_business_hours_with_offset_and_holidays() {
+        // 09:00-17:00 at UTC+2, i.e. 07:00-15:00 UTC
+        let hours = BusinessHours::new(120, time(9), time(17))
+            .with_holiday(NaiveDate::from_ymd_opt(2026, 10, 21).unwrap());
+
+        assert!(!hours.contains(at(19, 6, 59)));
+        assert!(hours.contains(at(19, 7, 0)));
+        assert!(hours.contains(at(19, 14, 59)));
+        assert!(!hours.contains(at(19, 15, 0)));
+        // Holiday and weekend
+        assert!(!hours.contains(at(21, 10, 0)));
+        assert!(!hours.contains(at(24, 10, 0)));
+        // 23:30 UTC Sunday is 01:30 Monday local, still before opening
+        assert!(!hours.contains(at(18, 23, 30)));
+    }
+
+    #[test]
+    fn test_overnight_shift_belongs_to_its_start_day() {
+        let night = BusinessHours::new(0, time(22), time(6));
+
+        assert!(night.contains(at(23, 23, 0)));
+        // Friday's shift runs into Saturday morning
+        assert!(night.contains(at(24, 5, 0)));
+        assert!(!night.contains(at(24, 6, 0)));
+        // No shift starts on Sunday, so Monday morning is outside it
+        assert!(!night.contains(at(19, 2, 0)));
+        assert!(night.contains(at(20, 2, 0)));
+        assert!(!night.contains(at(20, 12, 0)));
+    }
+
+    #[test]
+    fn test_route_schedules() {
+        let hours = BusinessHours::default().with_days(vec![Weekday::Mon]);
+        let business = RouteSchedule::BusinessHours(hours.clone());
+        let after = RouteSchedule::AfterHours(hours);
+
+        for now in [at(19, 10, 0), at(19, 20, 0), at(20, 10, 0)] {
+            assert!(RouteSchedule::Always.is_active(now));
+            assert_ne!(business.is_active(now), after.is_active(now));
+        }
+        assert!(business.is_active(at(19, 10, 0)));
+        assert!(after.is_active(at(20, 10, 0)));
+    }
+}
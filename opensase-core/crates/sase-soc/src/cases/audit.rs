@@ -0,0 +1,95 @@
+//! Case audit trail
+//!
+//! Append-only record of every case mutation, kept separately from the
+//! analyst-facing timeline so it survives edits to the case itself.
+
+use chrono::{DateTime, Utc};
+
+/// Entries kept before the oldest are dropped
+const MAX_ENTRIES: usize = 100_000;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CaseAuditEntry {
+    pub id: String,
+    pub case_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub actor: String,
+    pub action: CaseAuditAction,
+    pub details: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseAuditAction {
+    Created,
+    AlertAdded,
+    StatusChanged,
+    PriorityChanged,
+    Assigned,
+    Unassigned,
+    Commented,
+    ObservableAdded,
+    TaskAdded,
+    TaskUpdated,
+    EvidenceAttached,
+    EvidenceDetached,
+    Linked,
+    Unlinked,
+    SlaBreached,
+    Resolved,
+    Reopened,
+}
+
+pub struct CaseAuditLog {
+    entries: parking_lot::RwLock<Vec<CaseAuditEntry>>,
+}
+
+impl CaseAuditLog {
+    pub fn new() -> Self {
+        Self {
+            entries: parking_lot::RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn record(&self, case_id: &str, actor: &str, action: CaseAuditAction, details: String) {
+        tracing::debug!("Case {} {:?} by {}: {}", case_id, action, actor, details);
+
+        let mut entries = self.entries.write();
+        entries.push(CaseAuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            case_id: case_id.to_string(),
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            action,
+            details,
+        });
+        if entries.len() > MAX_ENTRIES {
+            let excess = entries.len() - MAX_ENTRIES;
+            entries.drain(..excess);
+        }
+    }
+
+    /// Entries for one case, oldest first
+    pub fn for_case(&self, case_id: &str) -> Vec<CaseAuditEntry> {
+        self.entries.read()
+            .iter()
+            .filter(|e| e.case_id == case_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Entries at or after a time, oldest first
+    pub fn since(&self, since: DateTime<Utc>) -> Vec<CaseAuditEntry> {
+        self.entries.read()
+            .iter()
+            .filter(|e| e.timestamp >= since)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for CaseAuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
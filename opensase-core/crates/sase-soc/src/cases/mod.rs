@@ -0,0 +1,1180 @@
+//! Case Management
+//!
+//! Security incident case tracking and workflow.
+//!
+//! Every mutation goes through [`CaseManager`], which validates it, appends
+//! to the case timeline and records an entry in the audit trail. Cases move
+//! through their states as:
+//!
+//! ```text
+//!   New ─> Open ─> InProgress ─> Resolved ─> Closed
+//!           │  ↑       │  ↑          │          │
+//!           ↓  │       ↓  │          └── reopen ┘
+//!           OnHold <───┘  │
+//!              └──────────┘
+//! ```
+
+mod audit;
+mod sla;
+
+pub use audit::{CaseAuditAction, CaseAuditEntry, CaseAuditLog};
+pub use sla::{CaseSla, SlaBreach, SlaPolicy, SlaTargets};
+
+use crate::forensics::{Evidence, EvidenceType};
+use crate::{IndicatorType, SecurityAlert, Severity};
+use std::collections::HashMap;
+
+/// Case manager
+pub struct CaseManager {
+    /// Cases
+    cases: dashmap::DashMap<String, Case>,
+    /// Case templates
+    templates: dashmap::DashMap<String, CaseTemplate>,
+    /// SLA targets
+    sla_policy: SlaPolicy,
+    /// Audit trail
+    audit: CaseAuditLog,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Case {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub severity: Severity,
+    pub status: CaseStatus,
+    pub priority: CasePriority,
+    pub case_type: CaseType,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub closed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub owner: Option<String>,
+    pub assigned_to: Vec<String>,
+    pub alerts: Vec<String>,
+    pub observables: Vec<Observable>,
+    pub tasks: Vec<CaseTask>,
+    pub evidence: Vec<EvidenceRef>,
+    pub links: Vec<CaseLink>,
+    pub sla: CaseSla,
+    pub timeline: Vec<TimelineEvent>,
+    pub tags: Vec<String>,
+    pub custom_fields: HashMap<String, String>,
+    pub resolution: Option<CaseResolution>,
+    pub tenant_id: String,
+}
+
+impl Case {
+    /// Completed and total tasks, ignoring cancelled ones
+    pub fn task_progress(&self) -> (usize, usize) {
+        let tasks = self.tasks.iter().filter(|t| t.status != TaskStatus::Cancelled);
+        let total = tasks.clone().count();
+        let done = tasks.filter(|t| t.status == TaskStatus::Completed).count();
+        (done, total)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !matches!(self.status, CaseStatus::Resolved | CaseStatus::Closed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum CaseStatus {
+    New,
+    Open,
+    InProgress,
+    OnHold,
+    Resolved,
+    Closed,
+}
+
+impl CaseStatus {
+    /// Whether a case may move from this status to `to`
+    pub fn can_transition_to(self, to: CaseStatus) -> bool {
+        match (self, to) {
+            (from, to) if from == to => false,
+            (_, CaseStatus::New) => false,
+            // Reopen
+            (CaseStatus::Resolved | CaseStatus::Closed, CaseStatus::Open) => true,
+            (CaseStatus::Resolved, CaseStatus::Closed) => true,
+            (CaseStatus::Resolved | CaseStatus::Closed, _) => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum CasePriority {
+    P1, // Critical - immediate
+    P2, // High - 4 hours
+    P3, // Medium - 24 hours
+    P4, // Low - 72 hours
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum CaseType {
+    SecurityIncident,
+    DataBreach,
+    Malware,
+    Phishing,
+    InsiderThreat,
+    Vulnerability,
+    ComplianceViolation,
+    Other,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Observable {
+    pub id: String,
+    pub observable_type: ObservableType,
+    pub value: String,
+    pub tlp: Tlp,
+    pub is_ioc: bool,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum ObservableType {
+    IpAddress,
+    Domain,
+    Url,
+    Hash,
+    Email,
+    Filename,
+    Registry,
+    Hostname,
+    Username,
+    Other,
+}
+
+impl From<IndicatorType> for ObservableType {
+    fn from(indicator: IndicatorType) -> Self {
+        match indicator {
+            IndicatorType::IpAddress => Self::IpAddress,
+            IndicatorType::Domain => Self::Domain,
+            IndicatorType::Url => Self::Url,
+            IndicatorType::Hash => Self::Hash,
+            IndicatorType::Email => Self::Email,
+            IndicatorType::Username => Self::Username,
+            IndicatorType::FileName => Self::Filename,
+            IndicatorType::Registry => Self::Registry,
+            IndicatorType::Process | IndicatorType::Certificate => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum Tlp {
+    White,
+    Green,
+    Amber,
+    Red,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CaseTask {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: TaskStatus,
+    pub assigned_to: Option<String>,
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum TaskStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Cancelled,
+}
+
+/// Forensic evidence attached to a case
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EvidenceRef {
+    /// Evidence ID in the forensics collector
+    pub evidence_id: String,
+    pub collection_id: String,
+    pub evidence_type: EvidenceType,
+    pub source_host: String,
+    /// Hash at the time of attachment, for integrity checks
+    pub hash_sha256: String,
+    pub note: Option<String>,
+    pub attached_by: String,
+    pub attached_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Relationship to another case
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CaseLink {
+    pub case_id: String,
+    pub link_type: CaseLinkType,
+    pub linked_by: String,
+    pub linked_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum CaseLinkType {
+    Related,
+    DuplicateOf,
+    DuplicatedBy,
+    ParentOf,
+    ChildOf,
+}
+
+impl CaseLinkType {
+    /// The same link seen from the other case
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Related => Self::Related,
+            Self::DuplicateOf => Self::DuplicatedBy,
+            Self::DuplicatedBy => Self::DuplicateOf,
+            Self::ParentOf => Self::ChildOf,
+            Self::ChildOf => Self::ParentOf,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TimelineEvent {
+    pub id: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub event_type: TimelineEventType,
+    pub description: String,
+    pub actor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum TimelineEventType {
+    Created,
+    Updated,
+    StatusChange,
+    Assigned,
+    Comment,
+    AlertAdded,
+    TaskAdded,
+    TaskUpdated,
+    EvidenceAdded,
+    EvidenceRemoved,
+    Linked,
+    Escalated,
+    Resolved,
+    Reopened,
+    Closed,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CaseResolution {
+    pub resolution_type: ResolutionType,
+    pub summary: String,
+    pub root_cause: Option<String>,
+    pub lessons_learned: Option<String>,
+    pub resolved_by: String,
+    pub resolved_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum ResolutionType {
+    TruePositive,
+    FalsePositive,
+    Benign,
+    Inconclusive,
+    Duplicate,
+    NoAction,
+}
+
+#[derive(Clone)]
+pub struct CaseTemplate {
+    pub id: String,
+    pub name: String,
+    pub case_type: CaseType,
+    pub default_severity: Severity,
+    pub default_priority: CasePriority,
+    pub default_tasks: Vec<CaseTask>,
+    pub playbook_id: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum CaseError {
+    NotFound(String),
+    TaskNotFound(String),
+    EvidenceNotFound(String),
+    InvalidTransition { from: CaseStatus, to: CaseStatus },
+    Invalid(String),
+}
+
+impl std::fmt::Display for CaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "Case not found: {}", id),
+            Self::TaskNotFound(id) => write!(f, "Task not found: {}", id),
+            Self::EvidenceNotFound(id) => write!(f, "Evidence not attached: {}", id),
+            Self::InvalidTransition { from, to } => write!(f, "Cannot move case from {:?} to {:?}", from, to),
+            Self::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaseError {}
+
+impl CaseManager {
+    pub fn new() -> Self {
+        let manager = Self {
+            cases: dashmap::DashMap::new(),
+            templates: dashmap::DashMap::new(),
+            sla_policy: SlaPolicy::default(),
+            audit: CaseAuditLog::new(),
+        };
+        
+        manager.load_default_templates();
+        manager
+    }
+    
+    pub fn with_sla_policy(mut self, policy: SlaPolicy) -> Self {
+        self.sla_policy = policy;
+        self
+    }
+    
+    fn load_default_templates(&self) {
+        // Malware incident template
+        self.templates.insert("malware-incident".to_string(), CaseTemplate {
+            id: "malware-incident".to_string(),
+            name: "Malware Incident".to_string(),
+            case_type: CaseType::Malware,
+            default_severity: Severity::High,
+            default_priority: CasePriority::P2,
+            default_tasks: vec![
+                CaseTask {
+                    id: "1".to_string(),
+                    title: "Identify affected systems".to_string(),
+                    description: Some("List all systems with malware IOCs".to_string()),
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+                CaseTask {
+                    id: "2".to_string(),
+                    title: "Isolate infected hosts".to_string(),
+                    description: None,
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+                CaseTask {
+                    id: "3".to_string(),
+                    title: "Collect forensic evidence".to_string(),
+                    description: None,
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+                CaseTask {
+                    id: "4".to_string(),
+                    title: "Remediate and restore".to_string(),
+                    description: None,
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+            ],
+            playbook_id: Some("malware-response".to_string()),
+        });
+        
+        // Phishing template
+        self.templates.insert("phishing-incident".to_string(), CaseTemplate {
+            id: "phishing-incident".to_string(),
+            name: "Phishing Incident".to_string(),
+            case_type: CaseType::Phishing,
+            default_severity: Severity::Medium,
+            default_priority: CasePriority::P3,
+            default_tasks: vec![
+                CaseTask {
+                    id: "1".to_string(),
+                    title: "Identify recipients".to_string(),
+                    description: None,
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+                CaseTask {
+                    id: "2".to_string(),
+                    title: "Block malicious URLs".to_string(),
+                    description: None,
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+                CaseTask {
+                    id: "3".to_string(),
+                    title: "Reset compromised credentials".to_string(),
+                    description: None,
+                    status: TaskStatus::Pending,
+                    assigned_to: None,
+                    due_at: None,
+                    created_at: chrono::Utc::now(),
+                    completed_at: None,
+                },
+            ],
+            playbook_id: None,
+        });
+    }
+    
+    
+    /// Create case from alert
+    pub async fn create_from_alert(&self, alert: &SecurityAlert, template_id: Option<&str>) -> Case {
+        let template = template_id
+            .and_then(|id| self.templates.get(id))
+            .map(|t| t.clone());
+        
+        let (case_type, tasks) = if let Some(t) = &template {
+            (t.case_type, t.default_tasks.clone())
+        } else {
+            (CaseType::SecurityIncident, vec![])
+        };
+        
+        let mut case = self.new_case(
+            format!("{} - {}", alert.alert_type, &alert.id[..8.min(alert.id.len())]),
+            format!("Auto-created from alert {}", alert.id),
+            alert.severity,
+            case_type,
+            "Case created from security alert",
+            "system",
+        );
+        case.alerts.push(alert.id.clone());
+        case.tasks = tasks;
+        
+        self.insert_new(case, "system")
+    }
+    
+    /// Open a case by hand
+    pub async fn create_case(
+        &self,
+        title: &str,
+        description: &str,
+        severity: Severity,
+        case_type: CaseType,
+        actor: &str,
+    ) -> Case {
+        let mut case = self.new_case(
+            title.to_string(),
+            description.to_string(),
+            severity,
+            case_type,
+            "Case created",
+            actor,
+        );
+        case.owner = Some(actor.to_string());
+        
+        self.insert_new(case, actor)
+    }
+    
+    fn new_case(
+        &self,
+        title: String,
+        description: String,
+        severity: Severity,
+        case_type: CaseType,
+        created: &str,
+        actor: &str,
+    ) -> Case {
+        let now = chrono::Utc::now();
+        let priority = self.severity_to_priority(severity);
+        let sla = self.sla_policy.start(priority, now);
+        
+        Case {
+            id: uuid::Uuid::new_v4().to_string(),
+            title,
+            description,
+            severity,
+            status: CaseStatus::New,
+            priority,
+            case_type,
+            created_at: now,
+            updated_at: now,
+            closed_at: None,
+            due_at: Some(sla.resolution_due),
+            owner: None,
+            assigned_to: vec![],
+            alerts: vec![],
+            observables: vec![],
+            tasks: vec![],
+            evidence: vec![],
+            links: vec![],
+            sla,
+            timeline: vec![TimelineEvent {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp: now,
+                event_type: TimelineEventType::Created,
+                description: created.to_string(),
+                actor: Some(actor.to_string()),
+            }],
+            tags: vec![],
+            custom_fields: HashMap::new(),
+            resolution: None,
+            tenant_id: "default".to_string(),
+        }
+    }
+    
+    fn insert_new(&self, case: Case, actor: &str) -> Case {
+        self.cases.insert(case.id.clone(), case.clone());
+        self.audit.record(&case.id, actor, CaseAuditAction::Created, case.title.clone());
+        
+        tracing::info!("Created case {} ({:?})", case.id, case.priority);
+        
+        case
+    }
+    
+    fn severity_to_priority(&self, severity: Severity) -> CasePriority {
+        match severity {
+            Severity::Critical => CasePriority::P1,
+            Severity::High => CasePriority::P2,
+            Severity::Medium => CasePriority::P3,
+            _ => CasePriority::P4,
+        }
+    }
+    
+    /// Apply a change to a case, then log it to the timeline and audit trail.
+    /// The closure returns the change description.
+    fn mutate<F>(
+        &self,
+        case_id: &str,
+        actor: &str,
+        action: CaseAuditAction,
+        event_type: TimelineEventType,
+        change: F,
+    ) -> Result<Case, CaseError>
+    where
+        F: FnOnce(&mut Case, chrono::DateTime<chrono::Utc>) -> Result<String, CaseError>,
+    {
+        let now = chrono::Utc::now();
+        let mut case = self.cases.get_mut(case_id)
+            .ok_or_else(|| CaseError::NotFound(case_id.to_string()))?;
+        
+        let description = change(&mut case, now)?;
+        case.updated_at = now;
+        case.timeline.push(TimelineEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: now,
+            event_type,
+            description: description.clone(),
+            actor: Some(actor.to_string()),
+        });
+        let updated = case.clone();
+        drop(case);
+        
+        self.audit.record(case_id, actor, action, description);
+        Ok(updated)
+    }
+    
+    // =========================================================================
+    // State and Assignment
+    // =========================================================================
+    
+    /// Update case status
+    pub async fn update_status(&self, case_id: &str, status: CaseStatus, actor: &str) -> Result<Case, CaseError> {
+        let (action, event_type) = match status {
+            CaseStatus::Resolved => (CaseAuditAction::Resolved, TimelineEventType::Resolved),
+            CaseStatus::Closed => (CaseAuditAction::StatusChanged, TimelineEventType::Closed),
+            CaseStatus::Open if self.get(case_id).is_some_and(|c| !c.is_active()) => {
+                (CaseAuditAction::Reopened, TimelineEventType::Reopened)
+            }
+            _ => (CaseAuditAction::StatusChanged, TimelineEventType::StatusChange),
+        };
+        
+        self.mutate(case_id, actor, action, event_type, |case, now| {
+            Self::transition(case, status, now)
+        })
+    }
+    
+    /// Move a case to a new status, keeping its SLA clock in step
+    fn transition(case: &mut Case, to: CaseStatus, now: chrono::DateTime<chrono::Utc>) -> Result<String, CaseError> {
+        let from = case.status;
+        if !from.can_transition_to(to) {
+            return Err(CaseError::InvalidTransition { from, to });
+        }
+        
+        if from == CaseStatus::OnHold {
+            case.sla.resume(now);
+        }
+        match to {
+            CaseStatus::OnHold => case.sla.pause(now),
+            CaseStatus::Resolved | CaseStatus::Closed => {
+                case.sla.resolve(now);
+                case.closed_at.get_or_insert(now);
+            }
+            CaseStatus::Open if !case.is_active() => {
+                case.sla.reopen();
+                case.closed_at = None;
+                case.resolution = None;
+            }
+            _ => {}
+        }
+        // Leaving New is the first response
+        if from == CaseStatus::New {
+            case.sla.respond(now);
+        }
+        
+        case.status = to;
+        Ok(format!("{:?} → {:?}", from, to))
+    }
+    
+    /// Change priority; SLA deadlines move to the new targets
+    pub async fn set_priority(&self, case_id: &str, priority: CasePriority, actor: &str) -> Result<Case, CaseError> {
+        let policy = &self.sla_policy;
+        self.mutate(case_id, actor, CaseAuditAction::PriorityChanged, TimelineEventType::Updated, |case, _| {
+            let old = case.priority;
+            case.priority = priority;
+            case.sla.retarget(policy, priority);
+            case.due_at = Some(case.sla.resolution_due);
+            Ok(format!("Priority {:?} → {:?}", old, priority))
+        })
+    }
+    
+    /// Assign case; the first assignment of a new case acknowledges it
+    pub async fn assign(&self, case_id: &str, assignee: &str, actor: &str) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::Assigned, TimelineEventType::Assigned, |case, now| {
+            if case.assigned_to.iter().any(|a| a == assignee) {
+                return Err(CaseError::Invalid(format!("{} is already assigned", assignee)));
+            }
+            case.assigned_to.push(assignee.to_string());
+            case.owner.get_or_insert_with(|| assignee.to_string());
+            if case.status == CaseStatus::New {
+                Self::transition(case, CaseStatus::Open, now)?;
+            }
+            Ok(format!("Assigned to {}", assignee))
+        })
+    }
+    
+    /// Remove an assignee
+    pub async fn unassign(&self, case_id: &str, assignee: &str, actor: &str) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::Unassigned, TimelineEventType::Assigned, |case, _| {
+            let before = case.assigned_to.len();
+            case.assigned_to.retain(|a| a != assignee);
+            if case.assigned_to.len() == before {
+                return Err(CaseError::Invalid(format!("{} is not assigned", assignee)));
+            }
+            if case.owner.as_deref() == Some(assignee) {
+                case.owner = case.assigned_to.first().cloned();
+            }
+            Ok(format!("Unassigned {}", assignee))
+        })
+    }
+    
+    /// Add comment
+    pub async fn add_comment(&self, case_id: &str, comment: &str, actor: &str) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::Commented, TimelineEventType::Comment, |_, _| {
+            Ok(comment.to_string())
+        })
+    }
+    
+    /// Attach another alert
+    pub async fn add_alert(&self, case_id: &str, alert_id: &str, actor: &str) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::AlertAdded, TimelineEventType::AlertAdded, |case, _| {
+            if case.alerts.iter().any(|a| a == alert_id) {
+                return Err(CaseError::Invalid(format!("Alert {} is already on the case", alert_id)));
+            }
+            case.alerts.push(alert_id.to_string());
+            Ok(format!("Alert {} added", alert_id))
+        })
+    }
+    
+    /// Add observable
+    pub async fn add_observable(&self, case_id: &str, observable: Observable, actor: &str) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::ObservableAdded, TimelineEventType::Updated, |case, _| {
+            let description = format!("Observable {:?} {}", observable.observable_type, observable.value);
+            case.observables.push(observable);
+            Ok(description)
+        })
+    }
+    
+    /// Resolve case
+    pub async fn resolve(&self, case_id: &str, resolution: CaseResolution) -> Result<Case, CaseError> {
+        let actor = resolution.resolved_by.clone();
+        self.mutate(case_id, &actor, CaseAuditAction::Resolved, TimelineEventType::Resolved, |case, now| {
+            Self::transition(case, CaseStatus::Resolved, now)?;
+            let description = format!("Case resolved ({:?}): {}", resolution.resolution_type, resolution.summary);
+            case.resolution = Some(resolution);
+            Ok(description)
+        })
+    }
+    
+    // =========================================================================
+    // Tasks
+    // =========================================================================
+    
+    /// Add a checklist task
+    pub async fn add_task(
+        &self,
+        case_id: &str,
+        title: &str,
+        description: Option<&str>,
+        due_at: Option<chrono::DateTime<chrono::Utc>>,
+        actor: &str,
+    ) -> Result<CaseTask, CaseError> {
+        let task = CaseTask {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            description: description.map(String::from),
+            status: TaskStatus::Pending,
+            assigned_to: None,
+            due_at,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        };
+        
+        let added = task.clone();
+        self.mutate(case_id, actor, CaseAuditAction::TaskAdded, TimelineEventType::TaskAdded, |case, _| {
+            case.tasks.push(added);
+            Ok(format!("Task added: {}", title))
+        })?;
+        Ok(task)
+    }
+    
+    /// Move a task to a new status
+    pub async fn update_task(
+        &self,
+        case_id: &str,
+        task_id: &str,
+        status: TaskStatus,
+        actor: &str,
+    ) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::TaskUpdated, TimelineEventType::TaskUpdated, |case, now| {
+            let task = case.tasks.iter_mut()
+                .find(|t| t.id == task_id)
+                .ok_or_else(|| CaseError::TaskNotFound(task_id.to_string()))?;
+            let old = task.status;
+            task.status = status;
+            task.completed_at = (status == TaskStatus::Completed).then_some(now);
+            Ok(format!("Task {}: {:?} → {:?}", task.title, old, status))
+        })
+    }
+    
+    /// Assign a task to an analyst
+    pub async fn assign_task(
+        &self,
+        case_id: &str,
+        task_id: &str,
+        assignee: &str,
+        actor: &str,
+    ) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::TaskUpdated, TimelineEventType::TaskUpdated, |case, _| {
+            let task = case.tasks.iter_mut()
+                .find(|t| t.id == task_id)
+                .ok_or_else(|| CaseError::TaskNotFound(task_id.to_string()))?;
+            task.assigned_to = Some(assignee.to_string());
+            Ok(format!("Task {} assigned to {}", task.title, assignee))
+        })
+    }
+    
+    // =========================================================================
+    // Evidence
+    // =========================================================================
+    
+    /// Attach a forensics artifact
+    pub async fn attach_evidence(
+        &self,
+        case_id: &str,
+        evidence: &Evidence,
+        note: Option<&str>,
+        actor: &str,
+    ) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::EvidenceAttached, TimelineEventType::EvidenceAdded, |case, now| {
+            if case.evidence.iter().any(|e| e.evidence_id == evidence.id) {
+                return Err(CaseError::Invalid(format!("Evidence {} is already attached", evidence.id)));
+            }
+            case.evidence.push(EvidenceRef {
+                evidence_id: evidence.id.clone(),
+                collection_id: evidence.collection_id.clone(),
+                evidence_type: evidence.evidence_type,
+                source_host: evidence.source_host.clone(),
+                hash_sha256: evidence.hash_sha256.clone(),
+                note: note.map(String::from),
+                attached_by: actor.to_string(),
+                attached_at: now,
+            });
+            Ok(format!(
+                "{:?} from {} attached (sha256 {})",
+                evidence.evidence_type, evidence.source_host, evidence.hash_sha256
+            ))
+        })
+    }
+    
+    /// Detach evidence; the artifact itself stays in the forensics store
+    pub async fn detach_evidence(&self, case_id: &str, evidence_id: &str, actor: &str) -> Result<Case, CaseError> {
+        self.mutate(case_id, actor, CaseAuditAction::EvidenceDetached, TimelineEventType::EvidenceRemoved, |case, _| {
+            let before = case.evidence.len();
+            case.evidence.retain(|e| e.evidence_id != evidence_id);
+            if case.evidence.len() == before {
+                return Err(CaseError::EvidenceNotFound(evidence_id.to_string()));
+            }
+            Ok(format!("Evidence {} detached", evidence_id))
+        })
+    }
+    
+    // =========================================================================
+    // Linked Cases
+    // =========================================================================
+    
+    /// Link two cases; the other case gets the inverse link
+    pub async fn link_cases(
+        &self,
+        case_id: &str,
+        other_id: &str,
+        link_type: CaseLinkType,
+        actor: &str,
+    ) -> Result<Case, CaseError> {
+        if case_id == other_id {
+            return Err(CaseError::Invalid("A case cannot be linked to itself".to_string()));
+        }
+        if !self.cases.contains_key(other_id) {
+            return Err(CaseError::NotFound(other_id.to_string()));
+        }
+        
+        let updated = self.mutate(case_id, actor, CaseAuditAction::Linked, TimelineEventType::Linked, |case, now| {
+            Self::add_link(case, other_id, link_type, actor, now)
+        })?;
+        self.mutate(other_id, actor, CaseAuditAction::Linked, TimelineEventType::Linked, |case, now| {
+            Self::add_link(case, case_id, link_type.inverse(), actor, now)
+        })?;
+        Ok(updated)
+    }
+    
+    fn add_link(
+        case: &mut Case,
+        other_id: &str,
+        link_type: CaseLinkType,
+        actor: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<String, CaseError> {
+        if case.links.iter().any(|l| l.case_id == other_id) {
+            return Err(CaseError::Invalid(format!("Case is already linked to {}", other_id)));
+        }
+        case.links.push(CaseLink {
+            case_id: other_id.to_string(),
+            link_type,
+            linked_by: actor.to_string(),
+            linked_at: now,
+        });
+        Ok(format!("Linked {:?} {}", link_type, other_id))
+    }
+    
+    /// Remove the link between two cases, from both sides
+    pub async fn unlink_cases(&self, case_id: &str, other_id: &str, actor: &str) -> Result<Case, CaseError> {
+        let updated = self.mutate(case_id, actor, CaseAuditAction::Unlinked, TimelineEventType::Linked, |case, _| {
+            Self::remove_link(case, other_id)
+        })?;
+        let back = self.mutate(other_id, actor, CaseAuditAction::Unlinked, TimelineEventType::Linked, |case, _| {
+            Self::remove_link(case, case_id)
+        });
+        if let Err(e) = back {
+            tracing::warn!("Case {} had no link back to {}: {}", other_id, case_id, e);
+        }
+        Ok(updated)
+    }
+    
+    fn remove_link(case: &mut Case, other_id: &str) -> Result<String, CaseError> {
+        let before = case.links.len();
+        case.links.retain(|l| l.case_id != other_id);
+        if case.links.len() == before {
+            return Err(CaseError::Invalid(format!("Case is not linked to {}", other_id)));
+        }
+        Ok(format!("Unlinked {}", other_id))
+    }
+    
+    /// Cases linked to a case
+    pub fn linked_cases(&self, case_id: &str) -> Vec<(CaseLinkType, Case)> {
+        let Some(case) = self.get(case_id) else {
+            return Vec::new();
+        };
+        case.links.iter()
+            .filter_map(|l| self.get(&l.case_id).map(|c| (l.link_type, c)))
+            .collect()
+    }
+    
+    // =========================================================================
+    // SLA
+    // =========================================================================
+    
+    /// Mark cases past their SLA deadlines and escalate them; call
+    /// periodically. Each breach is reported once.
+    pub async fn check_sla(&self) -> Vec<(String, SlaBreach)> {
+        let now = chrono::Utc::now();
+        let mut breaches = Vec::new();
+        
+        for mut case in self.cases.iter_mut() {
+            if !case.is_active() {
+                continue;
+            }
+            let found = case.sla.check(now);
+            if found.is_empty() {
+                continue;
+            }
+            for breach in &found {
+                case.timeline.push(TimelineEvent {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    timestamp: now,
+                    event_type: TimelineEventType::Escalated,
+                    description: format!("{:?} SLA breached", breach),
+                    actor: Some("system".to_string()),
+                });
+                breaches.push((case.id.clone(), *breach));
+            }
+            case.updated_at = now;
+        }
+        
+        for (case_id, breach) in &breaches {
+            tracing::warn!("Case {} breached its {:?} SLA", case_id, breach);
+            self.audit.record(case_id, "system", CaseAuditAction::SlaBreached, format!("{:?} SLA breached", breach));
+        }
+        breaches
+    }
+    
+    /// Active cases that have missed a deadline
+    pub fn breached_cases(&self) -> Vec<Case> {
+        self.cases.iter()
+            .filter(|c| c.is_active() && (c.sla.response_breached || c.sla.resolution_breached))
+            .map(|c| c.clone())
+            .collect()
+    }
+    
+    // =========================================================================
+    // Queries
+    // =========================================================================
+    
+    /// Get case
+    pub fn get(&self, case_id: &str) -> Option<Case> {
+        self.cases.get(case_id).map(|c| c.clone())
+    }
+    
+    /// Audit trail for a case
+    pub fn audit_log(&self, case_id: &str) -> Vec<CaseAuditEntry> {
+        self.audit.for_case(case_id)
+    }
+    
+    /// Audit entries across all cases since a time
+    pub fn audit_since(&self, since: chrono::DateTime<chrono::Utc>) -> Vec<CaseAuditEntry> {
+        self.audit.since(since)
+    }
+    
+    /// Search cases
+    pub fn search(&self, query: CaseQuery) -> Vec<Case> {
+        self.cases.iter()
+            .filter(|c| {
+                if let Some(status) = query.status {
+                    if c.status != status { return false; }
+                }
+                if let Some(priority) = query.priority {
+                    if c.priority != priority { return false; }
+                }
+                if let Some(ref assignee) = query.assignee {
+                    if !c.assigned_to.contains(assignee) { return false; }
+                }
+                if let Some(case_type) = query.case_type {
+                    if c.case_type != case_type { return false; }
+                }
+                true
+            })
+            .map(|c| c.clone())
+            .take(query.limit)
+            .collect()
+    }
+    
+    /// Get active count
+    pub async fn get_active_count(&self) -> u64 {
+        self.cases.iter()
+            .filter(|c| matches!(c.status, CaseStatus::New | CaseStatus::Open | CaseStatus::InProgress))
+            .count() as u64
+    }
+    
+    /// Get MTTR (mean time to resolve, in hours), excluding time on hold
+    pub async fn get_mttr(&self) -> f64 {
+        Self::mean_hours(self.cases.iter().filter_map(|c| c.sla.time_to_resolve()))
+    }
+    
+    /// Get MTTA (mean time to acknowledge, in hours)
+    pub async fn get_mtta(&self) -> f64 {
+        Self::mean_hours(self.cases.iter().filter_map(|c| c.sla.time_to_respond()))
+    }
+    
+    fn mean_hours(durations: impl Iterator<Item = chrono::Duration>) -> f64 {
+        let (count, total_secs) = durations.fold((0u64, 0i64), |(n, sum), d| (n + 1, sum + d.num_seconds()));
+        if count > 0 {
+            (total_secs as f64 / count as f64) / 3600.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for CaseManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default)]
+pub struct CaseQuery {
+    pub status: Option<CaseStatus>,
+    pub priority: Option<CasePriority>,
+    pub assignee: Option<String>,
+    pub case_type: Option<CaseType>,
+    pub limit: usize,
+}
+
+impl CaseQuery {
+    pub fn new() -> Self {
+        Self {
+            limit: 100,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn open_case(manager: &CaseManager, severity: Severity) -> Case {
+        manager.create_case("Suspicious login", "VPN login from a new country", severity, CaseType::SecurityIncident, "alice").await
+    }
+
+    fn resolution(by: &str) -> CaseResolution {
+        CaseResolution {
+            resolution_type: ResolutionType::TruePositive,
+            summary: "Credentials reset".to_string(),
+            root_cause: None,
+            lessons_learned: None,
+            resolved_by: by.to_string(),
+            resolved_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        use CaseStatus::*;
+        assert!(New.can_transition_to(Open));
+        assert!(OnHold.can_transition_to(InProgress));
+        assert!(Resolved.can_transition_to(Closed));
+        assert!(Closed.can_transition_to(Open));
+        assert!(!Open.can_transition_to(New));
+        assert!(!Open.can_transition_to(Open));
+        assert!(!Closed.can_transition_to(InProgress));
+        assert!(!Closed.can_transition_to(Resolved));
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_updates_sla_and_audit() {
+        let manager = CaseManager::new();
+        let case = open_case(&manager, Severity::Critical).await;
+        assert_eq!((case.status, case.priority), (CaseStatus::New, CasePriority::P1));
+
+        let case = manager.assign(&case.id, "bob", "alice").await.unwrap();
+        assert_eq!(case.status, CaseStatus::Open);
+        assert!(case.sla.responded_at.is_some());
+        assert!(manager.assign(&case.id, "bob", "alice").await.is_err());
+
+        let case = manager.update_status(&case.id, CaseStatus::OnHold, "bob").await.unwrap();
+        assert!(case.sla.paused_at.is_some());
+        let case = manager.update_status(&case.id, CaseStatus::InProgress, "bob").await.unwrap();
+        assert!(case.sla.paused_at.is_none());
+
+        let case = manager.resolve(&case.id, resolution("bob")).await.unwrap();
+        assert!(!case.is_active());
+        assert!(case.closed_at.is_some());
+        assert!(matches!(
+            manager.update_status(&case.id, CaseStatus::InProgress, "bob").await,
+            Err(CaseError::InvalidTransition { from: CaseStatus::Resolved, to: CaseStatus::InProgress })
+        ));
+
+        let case = manager.update_status(&case.id, CaseStatus::Open, "alice").await.unwrap();
+        assert!(case.resolution.is_none() && case.closed_at.is_none() && case.sla.resolved_at.is_none());
+
+        let actions: Vec<CaseAuditAction> = manager.audit_log(&case.id).iter().map(|e| e.action).collect();
+        assert_eq!(actions, vec![
+            CaseAuditAction::Created,
+            CaseAuditAction::Assigned,
+            CaseAuditAction::StatusChanged,
+            CaseAuditAction::StatusChanged,
+            CaseAuditAction::Resolved,
+            CaseAuditAction::Reopened,
+        ]);
+        assert_eq!(manager.audit_log(&case.id)[4].actor, "bob");
+        assert!(manager.audit_since(chrono::Utc::now() + chrono::Duration::seconds(1)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tasks_and_links() {
+        let manager = CaseManager::new();
+        let case = open_case(&manager, Severity::Medium).await;
+        let duplicate = open_case(&manager, Severity::Low).await;
+
+        let reset = manager.add_task(&case.id, "Reset password", None, None, "alice").await.unwrap();
+        let notify = manager.add_task(&case.id, "Notify user", None, None, "alice").await.unwrap();
+        let audit = manager.add_task(&case.id, "Audit logins", None, None, "alice").await.unwrap();
+        manager.update_task(&case.id, &reset.id, TaskStatus::Completed, "bob").await.unwrap();
+        let updated = manager.update_task(&case.id, &audit.id, TaskStatus::Cancelled, "bob").await.unwrap();
+        assert_eq!(updated.task_progress(), (1, 2));
+        assert!(updated.tasks.iter().find(|t| t.id == notify.id).unwrap().completed_at.is_none());
+        assert!(matches!(
+            manager.update_task(&case.id, "missing", TaskStatus::Completed, "bob").await,
+            Err(CaseError::TaskNotFound(_))
+        ));
+
+        manager.link_cases(&duplicate.id, &case.id, CaseLinkType::DuplicateOf, "alice").await.unwrap();
+        assert!(manager.link_cases(&case.id, &duplicate.id, CaseLinkType::Related, "alice").await.is_err());
+        assert!(manager.link_cases(&case.id, &case.id, CaseLinkType::Related, "alice").await.is_err());
+        let linked = manager.linked_cases(&case.id);
+        assert_eq!(linked.len(), 1);
+        assert_eq!((linked[0].0, linked[0].1.id.as_str()), (CaseLinkType::DuplicatedBy, duplicate.id.as_str()));
+
+        manager.unlink_cases(&case.id, &duplicate.id, "alice").await.unwrap();
+        assert!(manager.linked_cases(&duplicate.id).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_sla_escalates_once() {
+        let immediate = SlaTargets { response_minutes: 0, resolution_minutes: 0 };
+        let manager = CaseManager::new().with_sla_policy(SlaPolicy {
+            p1: immediate,
+            p2: immediate,
+            p3: immediate,
+            p4: immediate,
+        });
+        let waiting = open_case(&manager, Severity::High).await;
+        let acknowledged = open_case(&manager, Severity::High).await;
+        manager.assign(&acknowledged.id, "bob", "alice").await.unwrap();
+        let resolved = open_case(&manager, Severity::High).await;
+        manager.resolve(&resolved.id, resolution("bob")).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        let mut breaches = manager.check_sla().await;
+        breaches.sort_by_key(|(id, breach)| (id == &acknowledged.id, *breach == SlaBreach::Resolution));
+        assert_eq!(breaches, vec![
+            (waiting.id.clone(), SlaBreach::Response),
+            (waiting.id.clone(), SlaBreach::Resolution),
+            (acknowledged.id.clone(), SlaBreach::Resolution),
+        ]);
+        assert!(manager.check_sla().await.is_empty());
+        assert_eq!(manager.breached_cases().len(), 2);
+
+        let escalations = manager.get(&waiting.id).unwrap().timeline.iter()
+            .filter(|e| e.event_type == TimelineEventType::Escalated)
+            .count();
+        assert_eq!(escalations, 2);
+        assert_eq!(manager.audit_log(&waiting.id).last().unwrap().action, CaseAuditAction::SlaBreached);
+    }
+}
@@ -0,0 +1,201 @@
+//! Case SLA timers
+//!
+//! Each case carries a response and a resolution deadline set from its
+//! priority. The clock stops while a case is on hold, and both deadlines
+//! move out by the time spent paused.
+
+use super::CasePriority;
+use chrono::{DateTime, Duration, Utc};
+
+/// Response and resolution targets per priority
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SlaPolicy {
+    pub p1: SlaTargets,
+    pub p2: SlaTargets,
+    pub p3: SlaTargets,
+    pub p4: SlaTargets,
+}
+
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SlaTargets {
+    /// Minutes until a case must be acknowledged
+    pub response_minutes: i64,
+    /// Minutes until a case must be resolved
+    pub resolution_minutes: i64,
+}
+
+impl Default for SlaPolicy {
+    fn default() -> Self {
+        Self {
+            p1: SlaTargets { response_minutes: 15, resolution_minutes: 4 * 60 },
+            p2: SlaTargets { response_minutes: 60, resolution_minutes: 24 * 60 },
+            p3: SlaTargets { response_minutes: 4 * 60, resolution_minutes: 72 * 60 },
+            p4: SlaTargets { response_minutes: 24 * 60, resolution_minutes: 7 * 24 * 60 },
+        }
+    }
+}
+
+impl SlaPolicy {
+    pub fn targets(&self, priority: CasePriority) -> SlaTargets {
+        match priority {
+            CasePriority::P1 => self.p1,
+            CasePriority::P2 => self.p2,
+            CasePriority::P3 => self.p3,
+            CasePriority::P4 => self.p4,
+        }
+    }
+
+    /// Timers for a case opened at `started_at`
+    pub fn start(&self, priority: CasePriority, started_at: DateTime<Utc>) -> CaseSla {
+        let targets = self.targets(priority);
+        CaseSla {
+            started_at,
+            response_due: started_at + Duration::minutes(targets.response_minutes),
+            resolution_due: started_at + Duration::minutes(targets.resolution_minutes),
+            responded_at: None,
+            resolved_at: None,
+            paused_at: None,
+            paused_secs: 0,
+            response_breached: false,
+            resolution_breached: false,
+        }
+    }
+}
+
+/// SLA clock for one case
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CaseSla {
+    pub started_at: DateTime<Utc>,
+    pub response_due: DateTime<Utc>,
+    pub resolution_due: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub paused_at: Option<DateTime<Utc>>,
+    /// Total time on hold
+    pub paused_secs: i64,
+    pub response_breached: bool,
+    pub resolution_breached: bool,
+}
+
+/// Deadline missed by a case
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SlaBreach {
+    Response,
+    Resolution,
+}
+
+impl CaseSla {
+    /// Move both deadlines to match a new priority
+    pub fn retarget(&mut self, policy: &SlaPolicy, priority: CasePriority) {
+        let targets = policy.targets(priority);
+        let paused = Duration::seconds(self.paused_secs);
+        self.response_due = self.started_at + Duration::minutes(targets.response_minutes) + paused;
+        self.resolution_due = self.started_at + Duration::minutes(targets.resolution_minutes) + paused;
+    }
+
+    /// Mark the first response; later calls are ignored
+    pub fn respond(&mut self, at: DateTime<Utc>) {
+        if self.responded_at.is_none() {
+            self.responded_at = Some(at);
+        }
+    }
+
+    pub fn pause(&mut self, at: DateTime<Utc>) {
+        if self.paused_at.is_none() && self.resolved_at.is_none() {
+            self.paused_at = Some(at);
+        }
+    }
+
+    pub fn resume(&mut self, at: DateTime<Utc>) {
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused = at - paused_at;
+            self.paused_secs += paused.num_seconds();
+            if self.responded_at.is_none() {
+                self.response_due += paused;
+            }
+            self.resolution_due += paused;
+        }
+    }
+
+    pub fn resolve(&mut self, at: DateTime<Utc>) {
+        self.resume(at);
+        self.respond(at);
+        if self.resolved_at.is_none() {
+            self.resolved_at = Some(at);
+        }
+    }
+
+    /// Restart the resolution clock on a reopened case
+    pub fn reopen(&mut self) {
+        self.resolved_at = None;
+    }
+
+    /// Newly missed deadlines as of `now`; each is reported once
+    pub fn check(&mut self, now: DateTime<Utc>) -> Vec<SlaBreach> {
+        let mut breaches = Vec::new();
+        if self.paused_at.is_some() {
+            return breaches;
+        }
+        if !self.response_breached && self.responded_at.is_none() && now > self.response_due {
+            self.response_breached = true;
+            breaches.push(SlaBreach::Response);
+        }
+        if !self.resolution_breached && self.resolved_at.is_none() && now > self.resolution_due {
+            self.resolution_breached = true;
+            breaches.push(SlaBreach::Resolution);
+        }
+        breaches
+    }
+
+    /// Time from opening to first response
+    pub fn time_to_respond(&self) -> Option<Duration> {
+        self.responded_at.map(|at| at - self.started_at)
+    }
+
+    /// Time from opening to resolution, excluding time on hold
+    pub fn time_to_resolve(&self) -> Option<Duration> {
+        self.resolved_at.map(|at| at - self.started_at - Duration::seconds(self.paused_secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_moves_deadlines_and_breaches_report_once() {
+        let opened = Utc::now() - Duration::hours(10);
+        let mut sla = SlaPolicy::default().start(CasePriority::P1, opened);
+        assert_eq!(sla.response_due, opened + Duration::minutes(15));
+
+        // Two hours on hold before anyone responded
+        sla.pause(opened + Duration::minutes(5));
+        assert!(sla.check(Utc::now()).is_empty());
+        sla.resume(opened + Duration::minutes(125));
+        assert_eq!(sla.paused_secs, 7200);
+        assert_eq!(sla.response_due, opened + Duration::minutes(135));
+        assert_eq!(sla.resolution_due, opened + Duration::hours(6));
+
+        assert_eq!(sla.check(Utc::now()), vec![SlaBreach::Response, SlaBreach::Resolution]);
+        assert!(sla.check(Utc::now()).is_empty());
+
+        sla.resolve(opened + Duration::hours(7));
+        assert_eq!(sla.time_to_respond(), Some(Duration::hours(7)));
+        assert_eq!(sla.time_to_resolve(), Some(Duration::hours(5)));
+    }
+
+    #[test]
+    fn test_retarget_keeps_time_on_hold() {
+        let opened = Utc::now();
+        let policy = SlaPolicy::default();
+        let mut sla = policy.start(CasePriority::P4, opened);
+        sla.pause(opened);
+        sla.resume(opened + Duration::minutes(30));
+        sla.respond(opened + Duration::minutes(40));
+        sla.respond(opened + Duration::minutes(50));
+        assert_eq!(sla.responded_at, Some(opened + Duration::minutes(40)));
+
+        sla.retarget(&policy, CasePriority::P2);
+        assert_eq!(sla.resolution_due, opened + Duration::minutes(24 * 60 + 30));
+    }
+}
@@ -1,61 +1,199 @@
 //! Event Correlation
 //!
 //! Correlate and deduplicate security events.
+//!
+//! Rules group events by entity (source IP, user, host, ...) over sliding
+//! windows:
+//!
+//! - **Threshold** rules fire when a group sees `count` matching events, or
+//!   `count` distinct values of a field, within the window.
+//! - **Sequence** rules fire when a group walks through ordered stages
+//!   within the window, e.g. five failed logins followed by a success.
+//!
+//! A fired rule opens one correlated alert per group. Further matching
+//! events for that group are merged into it, raising its combined risk
+//! score, until the group has been quiet for the merge window.
+//!
+//! Before correlation, events are dropped when they duplicate a recent
+//! event, match a suppression rule, or come from a source over its rate
+//! limit.
 
-use crate::{SecurityEvent, SecurityAlert, Severity, AlertStatus, AlertEnrichment};
-use std::collections::HashMap;
+use crate::{SecurityEvent, SecurityAlert, Severity, AlertStatus, AlertEnrichment, IndicatorType};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub struct EventCorrelator {
     rules: dashmap::DashMap<String, CorrelationRule>,
-    active_chains: dashmap::DashMap<String, EventChain>,
+    /// Sliding-window state per `rule:group`
+    windows: dashmap::DashMap<String, GroupWindow>,
+    /// Correlated alerts still absorbing events, per `rule:group`
+    open_alerts: dashmap::DashMap<String, OpenAlert>,
     dedup_window: dashmap::DashMap<String, DedupEntry>,
+    suppressions: dashmap::DashMap<String, SuppressionRule>,
+    /// Event counts per source in the current noisy-source window
+    source_rates: dashmap::DashMap<String, SourceRate>,
+    regex_cache: dashmap::DashMap<String, Option<regex::Regex>>,
+    config: CorrelatorConfig,
     stats: CorrelatorStats,
 }
 
 struct CorrelatorStats {
     events_correlated: std::sync::atomic::AtomicU64,
     events_deduplicated: std::sync::atomic::AtomicU64,
+    events_suppressed: std::sync::atomic::AtomicU64,
     alerts_generated: std::sync::atomic::AtomicU64,
+    alerts_merged: std::sync::atomic::AtomicU64,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub struct CorrelatorConfig {
+    /// Re-delivered copies of an event within this window are dropped
+    pub dedup_window_secs: u64,
+    /// A correlated alert absorbs matching events until its group has been
+    /// quiet this long
+    pub merge_window_secs: u64,
+    /// Events a single source may send per rate window before the excess
+    /// is suppressed; 0 disables the limit
+    pub noisy_source_limit: u64,
+    pub noisy_source_window_secs: u64,
+}
+
+impl Default for CorrelatorConfig {
+    fn default() -> Self {
+        Self {
+            dedup_window_secs: 300,
+            merge_window_secs: 900,
+            noisy_source_limit: 1000,
+            noisy_source_window_secs: 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct CorrelationRule {
     pub id: String,
     pub name: String,
     pub description: String,
-    pub conditions: Vec<RuleCondition>,
-    pub threshold: CorrelationThreshold,
+    pub logic: CorrelationLogic,
+    /// Fields whose values identify the entity, e.g. `source.ip` or `user`;
+    /// events missing any of them are not correlated by this rule
+    pub group_by: Vec<String>,
+    pub window_secs: u64,
+    /// Alert type of the correlated alert, matched by SOAR triggers
+    pub alert_type: String,
     pub output_severity: Severity,
-    pub mitre_attack: Vec<String>,
+    pub mitre_tactics: Vec<String>,
+    pub mitre_techniques: Vec<String>,
     pub enabled: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub enum CorrelationLogic {
+    /// `count` matching events, or `count` distinct values of `distinct`
+    Threshold {
+        conditions: Vec<RuleCondition>,
+        count: u64,
+        distinct: Option<String>,
+    },
+    /// Ordered stages completed within the window
+    Sequence { stages: Vec<SequenceStage> },
+}
+
+#[derive(Clone, Debug)]
+pub struct SequenceStage {
+    pub name: String,
+    pub conditions: Vec<RuleCondition>,
+    /// Matching events needed before the next stage can start
+    pub min_count: u64,
+}
+
+#[derive(Clone, Debug)]
 pub struct RuleCondition {
     pub field: String,
     pub operator: ConditionOp,
     pub value: String,
 }
 
-#[derive(Clone, Copy)]
+impl RuleCondition {
+    pub fn new(field: &str, operator: ConditionOp, value: &str) -> Self {
+        Self { field: field.to_string(), operator, value: value.to_string() }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum ConditionOp {
     Equals, NotEquals, Contains, Regex, GreaterThan, LessThan,
 }
 
-#[derive(Clone)]
-pub struct CorrelationThreshold {
-    pub count: u64,
-    pub time_window_secs: u64,
-    pub group_by: Vec<String>,
+/// Drops matching events before correlation
+#[derive(Clone, Debug)]
+pub struct SuppressionRule {
+    pub id: String,
+    pub reason: String,
+    pub conditions: Vec<RuleCondition>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SuppressionReason {
+    /// Same event seen within the dedup window
+    Duplicate,
+    /// Matched a suppression rule
+    Rule(String),
+    /// Source exceeded its event rate
+    NoisySource(String),
+}
+
+/// What correlating one event produced
+#[derive(Clone, Debug, Default)]
+pub struct CorrelationResult {
+    /// Alerts opened by this event
+    pub new_alerts: Vec<SecurityAlert>,
+    /// Open alerts this event was merged into
+    pub updated_alerts: Vec<SecurityAlert>,
+    /// Set when the event was dropped before correlation
+    pub suppressed: Option<SuppressionReason>,
+}
+
+impl CorrelationResult {
+    /// Whether the event ended up in any correlated alert
+    pub fn is_correlated(&self) -> bool {
+        !self.new_alerts.is_empty() || !self.updated_alerts.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct CorrelatorMetrics {
+    pub events_correlated: u64,
+    pub events_deduplicated: u64,
+    pub events_suppressed: u64,
+    pub alerts_generated: u64,
+    pub alerts_merged: u64,
+    pub open_alerts: usize,
 }
 
+/// Event kept in a group's window
 #[derive(Clone)]
-struct EventChain {
-    rule_id: String,
-    group_key: String,
-    events: Vec<String>,
-    first_seen: chrono::DateTime<chrono::Utc>,
-    last_seen: chrono::DateTime<chrono::Utc>,
+struct WindowEvent {
+    id: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    severity: Severity,
+    /// Value of the rule's distinct field, for distinct-count thresholds
+    distinct: Option<String>,
+}
+
+#[derive(Default)]
+struct GroupWindow {
+    events: VecDeque<WindowEvent>,
+    /// Sequence rules: current stage and events matched per stage
+    stage: usize,
+    stage_counts: Vec<u64>,
+    last_seen: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+struct OpenAlert {
+    alert: SecurityAlert,
+    severities: Vec<Severity>,
+    last_update: chrono::DateTime<chrono::Utc>,
 }
 
 struct DedupEntry {
@@ -65,175 +203,384 @@ struct DedupEntry {
     last_seen: chrono::DateTime<chrono::Utc>,
 }
 
+struct SourceRate {
+    window_start: chrono::DateTime<chrono::Utc>,
+    count: u64,
+    suppressed: u64,
+}
+
 impl EventCorrelator {
     pub fn new() -> Self {
+        Self::with_config(CorrelatorConfig::default())
+    }
+
+    pub fn with_config(config: CorrelatorConfig) -> Self {
         let correlator = Self {
             rules: dashmap::DashMap::new(),
-            active_chains: dashmap::DashMap::new(),
+            windows: dashmap::DashMap::new(),
+            open_alerts: dashmap::DashMap::new(),
             dedup_window: dashmap::DashMap::new(),
+            suppressions: dashmap::DashMap::new(),
+            source_rates: dashmap::DashMap::new(),
+            regex_cache: dashmap::DashMap::new(),
+            config,
             stats: CorrelatorStats {
                 events_correlated: std::sync::atomic::AtomicU64::new(0),
                 events_deduplicated: std::sync::atomic::AtomicU64::new(0),
+                events_suppressed: std::sync::atomic::AtomicU64::new(0),
                 alerts_generated: std::sync::atomic::AtomicU64::new(0),
+                alerts_merged: std::sync::atomic::AtomicU64::new(0),
             },
         };
         correlator.load_default_rules();
         correlator
     }
-    
+
     fn load_default_rules(&self) {
         // Brute force rule
-        self.rules.insert("brute-force".to_string(), CorrelationRule {
+        self.register_rule(CorrelationRule {
             id: "brute-force".to_string(),
             name: "Brute Force Attack".to_string(),
             description: "Multiple failed logins from same source".to_string(),
-            conditions: vec![
-                RuleCondition {
-                    field: "event_type".to_string(),
-                    operator: ConditionOp::Equals,
-                    value: "AuthenticationFailure".to_string(),
-                },
-            ],
-            threshold: CorrelationThreshold {
+            logic: CorrelationLogic::Threshold {
+                conditions: vec![
+                    RuleCondition::new("event_type", ConditionOp::Equals, "AuthenticationFailure"),
+                ],
                 count: 5,
-                time_window_secs: 300,
-                group_by: vec!["source.ip".to_string()],
+                distinct: None,
             },
+            group_by: vec!["source.ip".to_string()],
+            window_secs: 300,
+            alert_type: "BruteForceAttempt".to_string(),
             output_severity: Severity::High,
-            mitre_attack: vec!["T1110".to_string()],
+            mitre_tactics: vec!["TA0006".to_string()],
+            mitre_techniques: vec!["T1110".to_string()],
+            enabled: true,
+        });
+
+        // Brute force followed by a successful login for the same user
+        self.register_rule(CorrelationRule {
+            id: "brute-force-success".to_string(),
+            name: "Brute Force Then Successful Login".to_string(),
+            description: "Failed logins followed by a successful login for the same user".to_string(),
+            logic: CorrelationLogic::Sequence {
+                stages: vec![
+                    SequenceStage {
+                        name: "failures".to_string(),
+                        conditions: vec![
+                            RuleCondition::new("event_type", ConditionOp::Equals, "AuthenticationFailure"),
+                        ],
+                        min_count: 5,
+                    },
+                    SequenceStage {
+                        name: "success".to_string(),
+                        conditions: vec![
+                            RuleCondition::new("raw_data.outcome", ConditionOp::Equals, "success"),
+                        ],
+                        min_count: 1,
+                    },
+                ],
+            },
+            group_by: vec!["user".to_string()],
+            window_secs: 900,
+            alert_type: "AccountCompromise".to_string(),
+            output_severity: Severity::Critical,
+            mitre_tactics: vec!["TA0006".to_string(), "TA0001".to_string()],
+            mitre_techniques: vec!["T1110".to_string(), "T1078".to_string()],
             enabled: true,
         });
-        
-        // Port scan rule
-        self.rules.insert("port-scan".to_string(), CorrelationRule {
+
+        // Port scan rule: one source probing many destination ports
+        self.register_rule(CorrelationRule {
             id: "port-scan".to_string(),
             name: "Port Scan Detection".to_string(),
             description: "Multiple connection attempts to different ports".to_string(),
-            conditions: vec![
-                RuleCondition {
-                    field: "event_type".to_string(),
-                    operator: ConditionOp::Equals,
-                    value: "PortScan".to_string(),
-                },
-            ],
-            threshold: CorrelationThreshold {
+            logic: CorrelationLogic::Threshold {
+                conditions: vec![
+                    RuleCondition::new("event_type", ConditionOp::Equals, "PortScan"),
+                ],
                 count: 20,
-                time_window_secs: 60,
-                group_by: vec!["source.ip".to_string()],
+                distinct: Some("destination.port".to_string()),
             },
+            group_by: vec!["source.ip".to_string()],
+            window_secs: 60,
+            alert_type: "PortScan".to_string(),
             output_severity: Severity::Medium,
-            mitre_attack: vec!["T1046".to_string()],
+            mitre_tactics: vec!["TA0007".to_string()],
+            mitre_techniques: vec!["T1046".to_string()],
             enabled: true,
         });
-        
+
         // Data exfiltration rule
-        self.rules.insert("data-exfil".to_string(), CorrelationRule {
+        self.register_rule(CorrelationRule {
             id: "data-exfil".to_string(),
             name: "Data Exfiltration".to_string(),
             description: "Large data transfer to external destination".to_string(),
-            conditions: vec![
-                RuleCondition {
-                    field: "event_type".to_string(),
-                    operator: ConditionOp::Equals,
-                    value: "DataExfiltration".to_string(),
-                },
-            ],
-            threshold: CorrelationThreshold {
+            logic: CorrelationLogic::Threshold {
+                conditions: vec![
+                    RuleCondition::new("event_type", ConditionOp::Equals, "DataExfiltration"),
+                ],
                 count: 3,
-                time_window_secs: 3600,
-                group_by: vec!["source.ip".to_string(), "destination.ip".to_string()],
+                distinct: None,
             },
+            group_by: vec!["source.ip".to_string(), "destination.ip".to_string()],
+            window_secs: 3600,
+            alert_type: "DataExfiltration".to_string(),
             output_severity: Severity::Critical,
-            mitre_attack: vec!["T1041".to_string()],
+            mitre_tactics: vec!["TA0010".to_string()],
+            mitre_techniques: vec!["T1041".to_string()],
             enabled: true,
         });
     }
-    
+
+    /// Correlate an event; returns the first alert it opened
     pub async fn process(&self, event: &SecurityEvent) -> Option<SecurityAlert> {
-        // Deduplication
-        let event_hash = self.compute_hash(event);
-        if self.is_duplicate(&event_hash) {
-            self.stats.events_deduplicated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-            return None;
+        self.correlate(event).new_alerts.into_iter().next()
+    }
+
+    /// Correlate an event against every enabled rule
+    pub fn correlate(&self, event: &SecurityEvent) -> CorrelationResult {
+        if let Some(reason) = self.suppression_reason(event) {
+            let counter = match reason {
+                SuppressionReason::Duplicate => &self.stats.events_deduplicated,
+                _ => &self.stats.events_suppressed,
+            };
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            return CorrelationResult { suppressed: Some(reason), ..Default::default() };
+        }
+
+        let rules: Vec<CorrelationRule> = self.rules.iter()
+            .filter(|r| r.enabled)
+            .map(|r| r.clone())
+            .collect();
+
+        let mut result = CorrelationResult::default();
+        for rule in rules {
+            let Some(group_key) = self.compute_group_key(&rule, event) else { continue };
+            let key = format!("{}:{}", rule.id, group_key);
+
+            // An open alert for this group absorbs further matching events
+            if self.matches_rule(&rule, event) {
+                if let Some(alert) = self.merge_into_open(&key, event) {
+                    result.updated_alerts.push(alert);
+                    continue;
+                }
+            }
+
+            let Some(fired) = self.advance_window(&rule, &key, event) else { continue };
+            result.new_alerts.push(self.open_alert(&rule, &key, &group_key, fired));
         }
-        
-        // Check correlation rules
-        for rule in self.rules.iter() {
-            if !rule.enabled { continue; }
-            if !self.matches_conditions(&rule, event) { continue; }
-            
-            let group_key = self.compute_group_key(&rule, event);
-            let chain_key = format!("{}:{}", rule.id, group_key);
-            
-            // Update or create chain
-            let should_alert = {
-                let mut chain = self.active_chains.entry(chain_key.clone()).or_insert_with(|| EventChain {
-                    rule_id: rule.id.clone(),
-                    group_key: group_key.clone(),
-                    events: vec![],
-                    first_seen: chrono::Utc::now(),
-                    last_seen: chrono::Utc::now(),
+
+        self.stats.events_correlated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        result
+    }
+
+    /// Feed the event into the rule's window; returns the window's events
+    /// when the rule fires
+    fn advance_window(&self, rule: &CorrelationRule, key: &str, event: &SecurityEvent) -> Option<Vec<WindowEvent>> {
+        let window = chrono::Duration::seconds(rule.window_secs as i64);
+
+        match &rule.logic {
+            CorrelationLogic::Threshold { conditions, count, distinct } => {
+                if !self.matches_conditions(conditions, event) {
+                    return None;
+                }
+                let mut state = self.windows.entry(key.to_string()).or_default();
+                state.events.push_back(WindowEvent {
+                    id: event.id.clone(),
+                    timestamp: event.timestamp,
+                    severity: event.severity,
+                    distinct: distinct.as_ref().and_then(|f| self.get_field_value(event, f)),
                 });
-                
-                chain.events.push(event.id.clone());
-                chain.last_seen = chrono::Utc::now();
-                
-                // Check threshold
-                let window_start = chrono::Utc::now() - chrono::Duration::seconds(rule.threshold.time_window_secs as i64);
-                if chain.first_seen >= window_start && chain.events.len() as u64 >= rule.threshold.count {
-                    true
-                } else {
-                    false
+                state.last_seen = Some(event.timestamp);
+
+                // Slide the window up to the newest event
+                let newest = state.events.iter().map(|e| e.timestamp).max().unwrap_or(event.timestamp);
+                state.events.retain(|e| newest - e.timestamp <= window);
+
+                let observed = match distinct {
+                    Some(_) => state.events.iter()
+                        .filter_map(|e| e.distinct.as_deref())
+                        .collect::<HashSet<_>>()
+                        .len() as u64,
+                    None => state.events.len() as u64,
+                };
+                if observed < *count {
+                    return None;
                 }
-            };
-            
-            if should_alert {
-                self.stats.alerts_generated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                
-                // Get event IDs from chain
-                let event_ids = self.active_chains.get(&chain_key)
-                    .map(|c| c.events.clone())
-                    .unwrap_or_default();
-                
-                // Clear chain
-                self.active_chains.remove(&chain_key);
-                
-                return Some(SecurityAlert {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    events: event_ids,
-                    alert_type: rule.name.clone(),
-                    severity: rule.output_severity,
-                    status: AlertStatus::New,
-                    created_at: chrono::Utc::now(),
-                    updated_at: chrono::Utc::now(),
-                    assigned_to: None,
-                    mitre_tactics: vec![],
-                    mitre_techniques: rule.mitre_attack.clone(),
-                    enrichment: AlertEnrichment::default(),
-                    case_id: None,
+                let fired = state.events.drain(..).collect();
+                drop(state);
+                self.windows.remove(key);
+                Some(fired)
+            }
+            CorrelationLogic::Sequence { stages } => {
+                if stages.is_empty() {
+                    return None;
+                }
+                let matched: Vec<bool> = stages.iter()
+                    .map(|s| self.matches_conditions(&s.conditions, event))
+                    .collect();
+                if !matched.iter().any(|m| *m) {
+                    return None;
+                }
+
+                let mut state = self.windows.entry(key.to_string()).or_default();
+                let started = state.events.front().map(|e| e.timestamp);
+                if started.is_some_and(|s| event.timestamp - s > window) {
+                    *state = GroupWindow::default();
+                }
+                if state.stage_counts.len() != stages.len() {
+                    state.stage_counts = vec![0; stages.len()];
+                }
+
+                let stage = state.stage;
+                let next_ready = stage + 1 < stages.len()
+                    && state.stage_counts[stage] >= stages[stage].min_count
+                    && matched[stage + 1];
+                if next_ready {
+                    state.stage = stage + 1;
+                } else if !matched[stage] {
+                    return None;
+                }
+
+                let current = state.stage;
+                state.stage_counts[current] += 1;
+                state.events.push_back(WindowEvent {
+                    id: event.id.clone(),
+                    timestamp: event.timestamp,
+                    severity: event.severity,
+                    distinct: None,
                 });
+                state.last_seen = Some(event.timestamp);
+
+                let complete = current + 1 == stages.len()
+                    && state.stage_counts[current] >= stages[current].min_count;
+                if !complete {
+                    return None;
+                }
+                let fired = state.events.drain(..).collect();
+                drop(state);
+                self.windows.remove(key);
+                Some(fired)
             }
         }
-        
-        self.stats.events_correlated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn open_alert(&self, rule: &CorrelationRule, key: &str, group_key: &str, events: Vec<WindowEvent>) -> SecurityAlert {
+        let now = chrono::Utc::now();
+        let severities: Vec<Severity> = events.iter().map(|e| e.severity).collect();
+        let alert = SecurityAlert {
+            id: uuid::Uuid::new_v4().to_string(),
+            events: events.into_iter().map(|e| e.id).collect(),
+            alert_type: rule.alert_type.clone(),
+            severity: rule.output_severity,
+            status: AlertStatus::New,
+            created_at: now,
+            updated_at: now,
+            assigned_to: None,
+            mitre_tactics: rule.mitre_tactics.clone(),
+            mitre_techniques: rule.mitre_techniques.clone(),
+            enrichment: AlertEnrichment {
+                risk_score: combined_risk_score(rule.output_severity, &severities),
+                ..Default::default()
+            },
+            case_id: None,
+        };
+
+        tracing::info!(
+            "Correlation rule {} fired for {} ({} events)",
+            rule.id, group_key, alert.events.len()
+        );
+        self.stats.alerts_generated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.open_alerts.insert(key.to_string(), OpenAlert {
+            alert: alert.clone(),
+            severities,
+            last_update: now,
+        });
+        alert
+    }
+
+    fn merge_into_open(&self, key: &str, event: &SecurityEvent) -> Option<SecurityAlert> {
+        let now = chrono::Utc::now();
+        let merge_window = chrono::Duration::seconds(self.config.merge_window_secs as i64);
+
+        let mut open = self.open_alerts.get_mut(key)?;
+        if now - open.last_update > merge_window {
+            drop(open);
+            self.open_alerts.remove(key);
+            return None;
+        }
+
+        if !open.alert.events.contains(&event.id) {
+            open.alert.events.push(event.id.clone());
+            open.severities.push(event.severity);
+        }
+        open.alert.severity = open.alert.severity.max(event.severity);
+        open.alert.enrichment.risk_score = combined_risk_score(open.alert.severity, &open.severities);
+        open.alert.updated_at = now;
+        open.last_update = now;
+
+        self.stats.alerts_merged.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Some(open.alert.clone())
+    }
+
+    /// Current state of an open correlated alert
+    pub fn get_alert(&self, alert_id: &str) -> Option<SecurityAlert> {
+        self.open_alerts.iter()
+            .find(|o| o.alert.id == alert_id)
+            .map(|o| o.alert.clone())
+    }
+
+    /// Correlated alerts still absorbing events
+    pub fn open_alerts(&self) -> Vec<SecurityAlert> {
+        self.open_alerts.iter().map(|o| o.alert.clone()).collect()
+    }
+
+    // =========================================================================
+    // Suppression and Deduplication
+    // =========================================================================
+
+    fn suppression_reason(&self, event: &SecurityEvent) -> Option<SuppressionReason> {
+        let now = chrono::Utc::now();
+        let rule = self.suppressions.iter()
+            .find(|s| s.expires_at.is_none_or(|e| e > now) && self.matches_conditions(&s.conditions, event))
+            .map(|s| s.id.clone());
+        if let Some(id) = rule {
+            return Some(SuppressionReason::Rule(id));
+        }
+
+        let event_hash = self.compute_hash(event);
+        if self.is_duplicate(&event_hash) {
+            return Some(SuppressionReason::Duplicate);
+        }
+
+        let source = source_key(event);
+        if self.is_noisy(&source) {
+            return Some(SuppressionReason::NoisySource(source));
+        }
         None
     }
-    
+
     fn compute_hash(&self, event: &SecurityEvent) -> String {
         use sha2::{Sha256, Digest};
-        let data = format!("{:?}{}{}",
+        // Re-deliveries of one event, not repeats of the same activity
+        let indicators: Vec<&str> = event.indicators.iter().map(|i| i.value.as_str()).collect();
+        let data = format!("{:?}{}{}{}{}{}",
             event.event_type,
             event.source.ip.as_deref().unwrap_or(""),
-            event.description
+            event.source.host.as_deref().unwrap_or(""),
+            event.description,
+            event.timestamp.timestamp_millis(),
+            indicators.join(",")
         );
         let hash = Sha256::digest(data.as_bytes());
         hex::encode(&hash[..8])
     }
-    
+
     fn is_duplicate(&self, hash: &str) -> bool {
         let now = chrono::Utc::now();
-        let window = chrono::Duration::minutes(5);
-        
+        let window = chrono::Duration::seconds(self.config.dedup_window_secs as i64);
+
         if let Some(mut entry) = self.dedup_window.get_mut(hash) {
             if now - entry.first_seen < window {
                 entry.count += 1;
@@ -241,7 +588,7 @@ impl EventCorrelator {
                 return true;
             }
         }
-        
+
         self.dedup_window.insert(hash.to_string(), DedupEntry {
             event_hash: hash.to_string(),
             count: 1,
@@ -250,65 +597,375 @@ impl EventCorrelator {
         });
         false
     }
-    
-    fn matches_conditions(&self, rule: &CorrelationRule, event: &SecurityEvent) -> bool {
-        for cond in &rule.conditions {
+
+    fn is_noisy(&self, source: &str) -> bool {
+        if self.config.noisy_source_limit == 0 {
+            return false;
+        }
+        let now = chrono::Utc::now();
+        let window = chrono::Duration::seconds(self.config.noisy_source_window_secs as i64);
+
+        let mut rate = self.source_rates.entry(source.to_string()).or_insert_with(|| SourceRate {
+            window_start: now,
+            count: 0,
+            suppressed: 0,
+        });
+        if now - rate.window_start > window {
+            if rate.suppressed > 0 {
+                tracing::warn!("Suppressed {} events from noisy source {}", rate.suppressed, source);
+            }
+            *rate = SourceRate { window_start: now, count: 0, suppressed: 0 };
+        }
+        rate.count += 1;
+        if rate.count > self.config.noisy_source_limit {
+            rate.suppressed += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Drop matching events before correlation
+    pub fn add_suppression(&self, rule: SuppressionRule) {
+        tracing::info!("Adding suppression {}: {}", rule.id, rule.reason);
+        self.suppressions.insert(rule.id.clone(), rule);
+    }
+
+    pub fn remove_suppression(&self, id: &str) -> Option<SuppressionRule> {
+        self.suppressions.remove(id).map(|(_, rule)| rule)
+    }
+
+    pub fn suppressions(&self) -> Vec<SuppressionRule> {
+        self.suppressions.iter().map(|s| s.clone()).collect()
+    }
+
+    // =========================================================================
+    // Conditions and Fields
+    // =========================================================================
+
+    fn matches_rule(&self, rule: &CorrelationRule, event: &SecurityEvent) -> bool {
+        match &rule.logic {
+            CorrelationLogic::Threshold { conditions, .. } => self.matches_conditions(conditions, event),
+            CorrelationLogic::Sequence { stages } => stages.iter()
+                .any(|s| self.matches_conditions(&s.conditions, event)),
+        }
+    }
+
+    fn matches_conditions(&self, conditions: &[RuleCondition], event: &SecurityEvent) -> bool {
+        for cond in conditions {
             let value = self.get_field_value(event, &cond.field);
             let matches = match cond.operator {
                 ConditionOp::Equals => value == Some(cond.value.clone()),
                 ConditionOp::NotEquals => value != Some(cond.value.clone()),
                 ConditionOp::Contains => value.map(|v| v.contains(&cond.value)).unwrap_or(false),
-                _ => true,
+                ConditionOp::Regex => value.map(|v| self.regex_matches(&cond.value, &v)).unwrap_or(false),
+                ConditionOp::GreaterThan => compare(value.as_deref(), &cond.value) == Some(std::cmp::Ordering::Greater),
+                ConditionOp::LessThan => compare(value.as_deref(), &cond.value) == Some(std::cmp::Ordering::Less),
             };
             if !matches { return false; }
         }
         true
     }
-    
+
+    fn regex_matches(&self, pattern: &str, value: &str) -> bool {
+        let compiled = self.regex_cache.entry(pattern.to_string())
+            .or_insert_with(|| {
+                let compiled = regex::Regex::new(pattern).ok();
+                if compiled.is_none() {
+                    tracing::warn!("Invalid correlation regex: {}", pattern);
+                }
+                compiled
+            });
+        compiled.as_ref().is_some_and(|r| r.is_match(value))
+    }
+
     fn get_field_value(&self, event: &SecurityEvent, field: &str) -> Option<String> {
+        let indicator = |kind: IndicatorType| {
+            event.indicators.iter().find(|i| i.indicator_type == kind).map(|i| i.value.clone())
+        };
         match field {
             "event_type" => Some(format!("{:?}", event.event_type)),
             "severity" => Some(format!("{:?}", event.severity)),
-            "source.ip" => event.source.ip.clone(),
+            "description" => Some(event.description.clone()),
+            "tenant_id" => event.tenant_id.clone(),
+            "source.system" => Some(event.source.system.clone()),
+            "source.component" => Some(event.source.component.clone()),
+            "source.ip" => event.source.ip.clone().or_else(|| indicator(IndicatorType::IpAddress)),
             "source.host" => event.source.host.clone(),
-            _ => None,
+            "user" => indicator(IndicatorType::Username).or_else(|| indicator(IndicatorType::Email)),
+            "domain" => indicator(IndicatorType::Domain),
+            "hash" => indicator(IndicatorType::Hash),
+            "tags" => Some(event.tags.join(",")),
+            _ => {
+                // Anything else is looked up in the raw event
+                let path = field.strip_prefix("raw_data.").unwrap_or(field);
+                let value = path.split('.').try_fold(&event.raw_data, |v, key| v.get(key))?;
+                match value {
+                    serde_json::Value::Null => None,
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    other => Some(other.to_string()),
+                }
+            }
         }
     }
-    
-    fn compute_group_key(&self, rule: &CorrelationRule, event: &SecurityEvent) -> String {
-        let parts: Vec<String> = rule.threshold.group_by.iter()
-            .filter_map(|f| self.get_field_value(event, f))
-            .collect();
-        parts.join(":")
+
+    fn compute_group_key(&self, rule: &CorrelationRule, event: &SecurityEvent) -> Option<String> {
+        let parts = rule.group_by.iter()
+            .map(|f| self.get_field_value(event, f))
+            .collect::<Option<Vec<String>>>()?;
+        Some(parts.join(":"))
     }
-    
+
     pub fn register_rule(&self, rule: CorrelationRule) {
         self.rules.insert(rule.id.clone(), rule);
     }
-    
+
+    pub fn rules(&self) -> Vec<CorrelationRule> {
+        let mut rules: Vec<CorrelationRule> = self.rules.iter().map(|r| r.clone()).collect();
+        rules.sort_by(|a, b| a.id.cmp(&b.id));
+        rules
+    }
+
+    pub fn remove_rule(&self, id: &str) -> Option<CorrelationRule> {
+        let removed = self.rules.remove(id).map(|(_, rule)| rule);
+        let prefix = format!("{}:", id);
+        self.windows.retain(|k, _| !k.starts_with(&prefix));
+        self.open_alerts.retain(|k, _| !k.starts_with(&prefix));
+        removed
+    }
+
+    pub fn stats(&self) -> CorrelatorMetrics {
+        use std::sync::atomic::Ordering;
+        CorrelatorMetrics {
+            events_correlated: self.stats.events_correlated.load(Ordering::Relaxed),
+            events_deduplicated: self.stats.events_deduplicated.load(Ordering::Relaxed),
+            events_suppressed: self.stats.events_suppressed.load(Ordering::Relaxed),
+            alerts_generated: self.stats.alerts_generated.load(Ordering::Relaxed),
+            alerts_merged: self.stats.alerts_merged.load(Ordering::Relaxed),
+            open_alerts: self.open_alerts.len(),
+        }
+    }
+
     pub async fn cleanup_expired(&self) {
         let now = chrono::Utc::now();
-        
+
         // Cleanup dedup window
+        let dedup_window = chrono::Duration::seconds(self.config.dedup_window_secs as i64);
         let expired_dedup: Vec<String> = self.dedup_window.iter()
-            .filter(|e| now - e.first_seen > chrono::Duration::minutes(10))
+            .filter(|e| now - e.first_seen > dedup_window)
             .map(|e| e.event_hash.clone())
             .collect();
         for key in expired_dedup {
             self.dedup_window.remove(&key);
         }
-        
-        // Cleanup chains
-        let expired_chains: Vec<String> = self.active_chains.iter()
-            .filter(|c| now - c.last_seen > chrono::Duration::hours(1))
-            .map(|c| format!("{}:{}", c.rule_id, c.group_key))
+
+        // Cleanup windows idle longer than their rule's window
+        let windows: HashMap<String, i64> = self.rules.iter()
+            .map(|r| (r.id.clone(), r.window_secs as i64))
             .collect();
-        for key in expired_chains {
-            self.active_chains.remove(&key);
-        }
+        self.windows.retain(|key, state| {
+            let rule_id = key.split(':').next().unwrap_or_default();
+            let window = windows.get(rule_id).copied().unwrap_or(3600);
+            state.last_seen.is_some_and(|seen| now - seen <= chrono::Duration::seconds(window))
+        });
+
+        // Close alerts whose group went quiet
+        let merge_window = chrono::Duration::seconds(self.config.merge_window_secs as i64);
+        self.open_alerts.retain(|_, open| now - open.last_update <= merge_window);
+
+        let rate_window = chrono::Duration::seconds(self.config.noisy_source_window_secs as i64);
+        self.source_rates.retain(|_, rate| now - rate.window_start <= rate_window);
+        self.suppressions.retain(|_, s| s.expires_at.is_none_or(|e| e > now));
     }
 }
 
 impl Default for EventCorrelator {
     fn default() -> Self { Self::new() }
 }
+
+/// Highest severity plus a bonus that grows with the number of correlated
+/// events
+fn combined_risk_score(rule_severity: Severity, severities: &[Severity]) -> f64 {
+    let peak = severities.iter().copied().max().unwrap_or(rule_severity).max(rule_severity);
+    let base = match peak {
+        Severity::Info => 10.0,
+        Severity::Low => 25.0,
+        Severity::Medium => 50.0,
+        Severity::High => 75.0,
+        Severity::Critical => 95.0,
+    };
+    let volume = (severities.len().max(1) as f64).log2() * 5.0;
+    (base + volume).min(100.0)
+}
+
+fn source_key(event: &SecurityEvent) -> String {
+    format!(
+        "{}/{}",
+        event.source.system,
+        event.source.host.as_deref().or(event.source.ip.as_deref()).unwrap_or(&event.source.component)
+    )
+}
+
+fn compare(value: Option<&str>, expected: &str) -> Option<std::cmp::Ordering> {
+    let value = value?;
+    match (value.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b),
+        _ => Some(value.cmp(expected)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventSource, EventType, Indicator};
+
+    fn event(event_type: EventType, ip: &str, raw_data: serde_json::Value) -> SecurityEvent {
+        SecurityEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            severity: Severity::Low,
+            source: EventSource {
+                system: "idp".to_string(),
+                component: "auth".to_string(),
+                host: None,
+                ip: Some(ip.to_string()),
+            },
+            timestamp: chrono::Utc::now(),
+            description: uuid::Uuid::new_v4().to_string(),
+            raw_data,
+            indicators: vec![],
+            tags: vec![],
+            tenant_id: None,
+        }
+    }
+
+    fn login(ip: &str, user: &str, outcome: &str) -> SecurityEvent {
+        let event_type = if outcome == "success" { EventType::UnauthorizedAccess } else { EventType::AuthenticationFailure };
+        let mut event = event(event_type, ip, serde_json::json!({ "outcome": outcome }));
+        event.indicators.push(Indicator {
+            indicator_type: IndicatorType::Username,
+            value: user.to_string(),
+            confidence: 1.0,
+            context: None,
+        });
+        event
+    }
+
+    fn alert_types(result: &CorrelationResult) -> Vec<&str> {
+        result.new_alerts.iter().map(|a| a.alert_type.as_str()).collect()
+    }
+
+    #[test]
+    fn test_threshold_fires_then_merges() {
+        let correlator = EventCorrelator::new();
+
+        for _ in 0..4 {
+            assert!(!correlator.correlate(&event(EventType::AuthenticationFailure, "203.0.113.9", serde_json::Value::Null)).is_correlated());
+        }
+        // Another source keeps its own window
+        assert!(!correlator.correlate(&event(EventType::AuthenticationFailure, "198.51.100.1", serde_json::Value::Null)).is_correlated());
+
+        let result = correlator.correlate(&event(EventType::AuthenticationFailure, "203.0.113.9", serde_json::Value::Null));
+        assert_eq!(alert_types(&result), vec!["BruteForceAttempt"]);
+        let alert = &result.new_alerts[0];
+        assert_eq!(alert.events.len(), 5);
+        assert_eq!(alert.severity, Severity::High);
+
+        let mut louder = event(EventType::AuthenticationFailure, "203.0.113.9", serde_json::Value::Null);
+        louder.severity = Severity::Critical;
+        let result = correlator.correlate(&louder);
+        assert!(result.new_alerts.is_empty());
+        assert_eq!(result.updated_alerts.len(), 1);
+        let merged = correlator.get_alert(&alert.id).unwrap();
+        assert_eq!(merged.events.len(), 6);
+        assert_eq!(merged.severity, Severity::Critical);
+        assert!(merged.enrichment.risk_score > alert.enrichment.risk_score);
+
+        let stats = correlator.stats();
+        assert_eq!((stats.alerts_generated, stats.alerts_merged, stats.open_alerts), (1, 1, 1));
+
+        correlator.remove_rule("brute-force");
+        assert!(correlator.open_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_sequence_needs_failures_before_success() {
+        let correlator = EventCorrelator::new();
+
+        // Too few failures: the success does not complete the sequence
+        correlator.correlate(&login("10.0.0.1", "bob", "failure"));
+        correlator.correlate(&login("10.0.0.2", "bob", "failure"));
+        assert!(!correlator.correlate(&login("10.0.0.3", "bob", "success")).is_correlated());
+
+        for i in 0..5 {
+            correlator.correlate(&login(&format!("10.0.1.{}", i), "alice", "failure"));
+        }
+        let result = correlator.correlate(&login("10.0.2.1", "alice", "success"));
+        assert_eq!(alert_types(&result), vec!["AccountCompromise"]);
+        assert_eq!(result.new_alerts[0].events.len(), 6);
+        assert_eq!(result.new_alerts[0].mitre_techniques, vec!["T1110", "T1078"]);
+    }
+
+    #[test]
+    fn test_distinct_threshold_counts_values() {
+        let correlator = EventCorrelator::new();
+        let probe = |port: u16| event(EventType::PortScan, "192.0.2.7", serde_json::json!({ "destination": { "port": port } }));
+
+        for _ in 0..25 {
+            assert!(correlator.correlate(&probe(443)).new_alerts.is_empty());
+        }
+        let fired: Vec<String> = (1..=20u16)
+            .flat_map(|port| correlator.correlate(&probe(port)).new_alerts)
+            .map(|a| a.alert_type)
+            .collect();
+        assert_eq!(fired, vec!["PortScan"]);
+    }
+
+    #[test]
+    fn test_duplicates_suppressions_and_noisy_sources() {
+        let correlator = EventCorrelator::with_config(CorrelatorConfig {
+            noisy_source_limit: 3,
+            ..Default::default()
+        });
+
+        let first = event(EventType::Custom, "192.0.2.1", serde_json::Value::Null);
+        assert!(correlator.correlate(&first).suppressed.is_none());
+        let redelivered = SecurityEvent { id: "copy".to_string(), ..first.clone() };
+        assert_eq!(correlator.correlate(&redelivered).suppressed, Some(SuppressionReason::Duplicate));
+
+        correlator.add_suppression(SuppressionRule {
+            id: "scanner".to_string(),
+            reason: "authorized scanner".to_string(),
+            conditions: vec![RuleCondition::new("source.ip", ConditionOp::Regex, r"^192\.0\.2\.2\d*$")],
+            expires_at: None,
+        });
+        correlator.add_suppression(SuppressionRule {
+            id: "expired".to_string(),
+            reason: "old window".to_string(),
+            conditions: vec![],
+            expires_at: Some(chrono::Utc::now() - chrono::Duration::seconds(1)),
+        });
+        assert_eq!(
+            correlator.correlate(&event(EventType::Custom, "192.0.2.20", serde_json::Value::Null)).suppressed,
+            Some(SuppressionReason::Rule("scanner".to_string()))
+        );
+
+        // The source already sent one event; the fourth one is over the limit
+        for _ in 0..2 {
+            assert!(correlator.correlate(&event(EventType::Custom, "192.0.2.1", serde_json::Value::Null)).suppressed.is_none());
+        }
+        assert_eq!(
+            correlator.correlate(&event(EventType::Custom, "192.0.2.1", serde_json::Value::Null)).suppressed,
+            Some(SuppressionReason::NoisySource("idp/192.0.2.1".to_string()))
+        );
+
+        let stats = correlator.stats();
+        assert_eq!((stats.events_deduplicated, stats.events_suppressed), (1, 2));
+    }
+
+    #[test]
+    fn test_combined_risk_score() {
+        assert_eq!(combined_risk_score(Severity::Medium, &[]), 50.0);
+        assert_eq!(combined_risk_score(Severity::Low, &[Severity::High; 4]), 85.0);
+        assert_eq!(combined_risk_score(Severity::Critical, &[Severity::Low; 16]), 100.0);
+    }
+}
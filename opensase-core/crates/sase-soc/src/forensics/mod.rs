@@ -0,0 +1,617 @@
+//! Forensics Collection
+//!
+//! Evidence collection and chain of custody.
+//!
+//! Collections are filled on demand or from a playbook step by pulling
+//! artifacts from registered [`ArtifactSource`]s: packet captures, flow
+//! snapshots, email samples and session recordings. Content goes to a
+//! content-addressed [`EvidenceStore`] under its SHA-256, and every
+//! artifact carries a hash-chained custody log ([`custody`]).
+
+pub mod custody;
+mod sources;
+mod store;
+
+pub use custody::{verify_chain, CustodyAction, CustodyEvent};
+pub use sources::{Artifact, ArtifactSource, CollectionTarget};
+pub use store::{sha256_hex, EvidenceStore, FileEvidenceStore, MemoryEvidenceStore};
+
+use crate::cases::CaseManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct ForensicsCollector {
+    collections: dashmap::DashMap<String, ForensicCollection>,
+    evidence: dashmap::DashMap<String, Evidence>,
+    store: Arc<dyn EvidenceStore>,
+    sources: dashmap::DashMap<String, Arc<dyn ArtifactSource>>,
+    /// Collected evidence is attached to the collection's case
+    cases: Option<Arc<CaseManager>>,
+    source_timeout: Duration,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ForensicCollection {
+    pub id: String,
+    pub case_id: String,
+    pub name: String,
+    pub status: CollectionStatus,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub collected_by: String,
+    pub evidence_ids: Vec<String>,
+    /// Sources that failed, with their errors
+    pub failures: Vec<(String, String)>,
+}
+
+#[derive(Clone, Copy, Debug, serde::Serialize, PartialEq, Eq)]
+pub enum CollectionStatus { Pending, InProgress, Completed, Failed }
+
+#[derive(Clone, serde::Serialize)]
+pub struct Evidence {
+    pub id: String,
+    pub collection_id: String,
+    pub evidence_type: EvidenceType,
+    pub source_host: String,
+    /// Content hash; also the content's address in the store
+    pub hash_sha256: String,
+    pub size_bytes: u64,
+    pub collected_at: chrono::DateTime<chrono::Utc>,
+    pub chain_of_custody: Vec<CustodyEvent>,
+    pub storage_path: String,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
+pub enum EvidenceType {
+    MemoryDump, DiskImage, LogFile, NetworkCapture,
+    ProcessList, Registry, FileArtifact, MalwareSample,
+    FlowSnapshot, EmailSample, SessionRecording,
+}
+
+/// What to collect, and about what
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CollectionRequest {
+    pub evidence_types: Vec<EvidenceType>,
+    pub target: CollectionTarget,
+}
+
+/// Outcome of integrity verification
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct IntegrityReport {
+    pub evidence_id: String,
+    /// Stored content still hashes to the recorded value; `None` when the
+    /// content is held outside the store
+    pub content_intact: Option<bool>,
+    /// Custody log links are all valid
+    pub chain_intact: bool,
+    /// First custody entry that fails verification
+    pub broken_at: Option<u64>,
+    pub checked_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl IntegrityReport {
+    pub fn is_intact(&self) -> bool {
+        self.content_intact != Some(false) && self.chain_intact
+    }
+}
+
+impl ForensicsCollector {
+    pub fn new() -> Self {
+        Self {
+            collections: dashmap::DashMap::new(),
+            evidence: dashmap::DashMap::new(),
+            store: Arc::new(MemoryEvidenceStore::new()),
+            sources: dashmap::DashMap::new(),
+            cases: None,
+            source_timeout: Duration::from_secs(120),
+        }
+    }
+
+    /// Store artifacts somewhere other than memory
+    pub fn with_store(mut self, store: Arc<dyn EvidenceStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Attach collected evidence to cases
+    pub fn with_case_manager(mut self, cases: Arc<CaseManager>) -> Self {
+        self.cases = Some(cases);
+        self
+    }
+
+    /// Longest a single source may take to collect
+    pub fn with_source_timeout(mut self, timeout: Duration) -> Self {
+        self.source_timeout = timeout;
+        self
+    }
+
+    /// Register a source; replaces any source with the same name
+    pub fn register_source(&self, source: Arc<dyn ArtifactSource>) {
+        self.sources.insert(source.name().to_string(), source);
+    }
+
+    /// Evidence types some registered source can collect
+    pub fn available_types(&self) -> Vec<EvidenceType> {
+        let mut types: Vec<EvidenceType> = Vec::new();
+        for source in self.sources.iter() {
+            for t in source.evidence_types() {
+                if !types.contains(&t) {
+                    types.push(t);
+                }
+            }
+        }
+        types
+    }
+
+    pub async fn create_collection(&self, case_id: &str, name: &str, actor: &str) -> String {
+        let collection = ForensicCollection {
+            id: uuid::Uuid::new_v4().to_string(),
+            case_id: case_id.to_string(),
+            name: name.to_string(),
+            status: CollectionStatus::Pending,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+            collected_by: actor.to_string(),
+            evidence_ids: vec![],
+            failures: vec![],
+        };
+        let id = collection.id.clone();
+        self.collections.insert(id.clone(), collection);
+        id
+    }
+
+    /// Pull artifacts from every source that offers a requested type into a
+    /// collection. A failing source is recorded on the collection and does
+    /// not stop the others; the collection fails only if nothing was
+    /// collected and some source failed.
+    pub async fn collect(
+        &self,
+        collection_id: &str,
+        request: &CollectionRequest,
+        actor: &str,
+    ) -> Result<Vec<Evidence>, ForensicsError> {
+        {
+            let mut collection = self.collections.get_mut(collection_id)
+                .ok_or_else(|| ForensicsError::NotFound(format!("collection {}", collection_id)))?;
+            collection.status = CollectionStatus::InProgress;
+        }
+
+        let sources: Vec<Arc<dyn ArtifactSource>> = self.sources.iter().map(|s| s.value().clone()).collect();
+        let mut collected = Vec::new();
+        let mut failures = Vec::new();
+
+        for evidence_type in &request.evidence_types {
+            let offering: Vec<_> = sources.iter()
+                .filter(|s| s.evidence_types().contains(evidence_type))
+                .collect();
+            if offering.is_empty() {
+                failures.push(("forensics".to_string(), format!("No source for {:?}", evidence_type)));
+                continue;
+            }
+
+            for source in offering {
+                let result = tokio::time::timeout(
+                    self.source_timeout,
+                    source.collect(*evidence_type, &request.target),
+                ).await;
+                let artifacts = match result {
+                    Ok(Ok(artifacts)) => artifacts,
+                    Ok(Err(e)) => {
+                        tracing::warn!("Forensics source {} failed for {:?}: {}", source.name(), evidence_type, e);
+                        failures.push((source.name().to_string(), e.to_string()));
+                        continue;
+                    }
+                    Err(_) => {
+                        tracing::warn!("Forensics source {} timed out for {:?}", source.name(), evidence_type);
+                        failures.push((source.name().to_string(), "Timed out".to_string()));
+                        continue;
+                    }
+                };
+
+                for artifact in artifacts {
+                    let notes = format!("From {} ({}): {}", source.name(), artifact.content_type, request.target.reason);
+                    match self.store_artifact(collection_id, artifact, source.name(), CustodyAction::Collected, actor, notes).await {
+                        Ok(evidence) => collected.push(evidence),
+                        Err(e) => failures.push((source.name().to_string(), e.to_string())),
+                    }
+                }
+            }
+        }
+
+        let case_id = {
+            let mut collection = self.collections.get_mut(collection_id)
+                .ok_or_else(|| ForensicsError::NotFound(format!("collection {}", collection_id)))?;
+            collection.status = if collected.is_empty() && !failures.is_empty() {
+                CollectionStatus::Failed
+            } else {
+                CollectionStatus::Completed
+            };
+            collection.completed_at = Some(chrono::Utc::now());
+            collection.failures.extend(failures);
+            collection.case_id.clone()
+        };
+
+        self.attach_to_case(&case_id, &collected, actor).await;
+        Ok(collected)
+    }
+
+    /// Open a collection for a case and fill it
+    pub async fn collect_for_case(
+        &self,
+        case_id: &str,
+        name: &str,
+        request: &CollectionRequest,
+        actor: &str,
+    ) -> Result<(String, Vec<Evidence>), ForensicsError> {
+        let collection_id = self.create_collection(case_id, name, actor).await;
+        let evidence = self.collect(&collection_id, request, actor).await?;
+        Ok((collection_id, evidence))
+    }
+
+    /// Add an artifact collected outside the registered sources
+    pub async fn ingest_artifact(
+        &self,
+        collection_id: &str,
+        artifact: Artifact,
+        actor: &str,
+        notes: Option<&str>,
+    ) -> Result<Evidence, ForensicsError> {
+        if !self.collections.contains_key(collection_id) {
+            return Err(ForensicsError::NotFound(format!("collection {}", collection_id)));
+        }
+        let notes = notes.map(String::from)
+            .unwrap_or_else(|| format!("Submitted by {} ({})", actor, artifact.content_type));
+        let evidence = self.store_artifact(collection_id, artifact, actor, CustodyAction::Registered, actor, notes).await?;
+
+        let case_id = self.collections.get(collection_id).map(|c| c.case_id.clone()).unwrap_or_default();
+        self.attach_to_case(&case_id, std::slice::from_ref(&evidence), actor).await;
+        Ok(evidence)
+    }
+
+    /// Register evidence whose content is held elsewhere. The custody log
+    /// supplied with it is re-sealed as a hash chain, followed by a
+    /// registration entry.
+    pub async fn add_evidence(&self, collection_id: &str, mut evidence: Evidence) {
+        let supplied = std::mem::take(&mut evidence.chain_of_custody);
+        for event in supplied {
+            custody::append(&mut evidence.chain_of_custody, &evidence.hash_sha256, event.action, &event.actor, event.notes);
+        }
+        custody::append(
+            &mut evidence.chain_of_custody,
+            &evidence.hash_sha256,
+            CustodyAction::Registered,
+            "system",
+            Some(format!("Stored at {}", evidence.storage_path)),
+        );
+
+        self.evidence.insert(evidence.id.clone(), evidence.clone());
+        if let Some(mut c) = self.collections.get_mut(collection_id) {
+            c.evidence_ids.push(evidence.id);
+        }
+    }
+
+    /// Read an artifact's content; the read is logged and the content is
+    /// checked against its hash
+    pub async fn read_content(&self, evidence_id: &str, actor: &str, purpose: &str) -> Result<Vec<u8>, ForensicsError> {
+        let hash = self.evidence.get(evidence_id)
+            .map(|e| e.hash_sha256.clone())
+            .ok_or_else(|| ForensicsError::NotFound(format!("evidence {}", evidence_id)))?;
+        let content = self.store.get(&hash).await?
+            .ok_or_else(|| ForensicsError::NotFound(format!("content {} of evidence {}", hash, evidence_id)))?;
+
+        if sha256_hex(&content) != hash {
+            self.log_custody(evidence_id, CustodyAction::Verified, actor, Some(format!("Content hash mismatch on read: {}", purpose)))?;
+            return Err(ForensicsError::IntegrityViolation(format!("content of evidence {} does not match its hash", evidence_id)));
+        }
+        self.log_custody(evidence_id, CustodyAction::Accessed, actor, Some(purpose.to_string()))?;
+        Ok(content)
+    }
+
+    /// Hand custody to another holder
+    pub fn transfer(&self, evidence_id: &str, from: &str, to: &str, notes: Option<&str>) -> Result<CustodyEvent, ForensicsError> {
+        let notes = match notes {
+            Some(n) => format!("Transferred to {}: {}", to, n),
+            None => format!("Transferred to {}", to),
+        };
+        self.log_custody(evidence_id, CustodyAction::Transferred, from, Some(notes))
+    }
+
+    /// Record an export (e.g. to legal or law enforcement)
+    pub fn record_export(&self, evidence_id: &str, actor: &str, destination: &str) -> Result<CustodyEvent, ForensicsError> {
+        self.log_custody(evidence_id, CustodyAction::Exported, actor, Some(format!("Exported to {}", destination)))
+    }
+
+    /// Check stored content and the custody log; the check itself is logged
+    pub async fn verify(&self, evidence_id: &str, actor: &str) -> Result<IntegrityReport, ForensicsError> {
+        let evidence = self.get_evidence(evidence_id)
+            .ok_or_else(|| ForensicsError::NotFound(format!("evidence {}", evidence_id)))?;
+        let content_intact = self.store.get(&evidence.hash_sha256).await?
+            .map(|content| sha256_hex(&content) == evidence.hash_sha256);
+        let broken_at = verify_chain(&evidence.chain_of_custody, &evidence.hash_sha256).err();
+
+        let report = IntegrityReport {
+            evidence_id: evidence_id.to_string(),
+            content_intact,
+            chain_intact: broken_at.is_none(),
+            broken_at,
+            checked_at: chrono::Utc::now(),
+        };
+        if !report.is_intact() {
+            tracing::error!("Evidence {} failed integrity verification: {:?}", evidence_id, report);
+        }
+        let outcome = if report.is_intact() { "intact".to_string() } else { format!("FAILED: {:?}", report) };
+        self.log_custody(evidence_id, CustodyAction::Verified, actor, Some(outcome))?;
+        Ok(report)
+    }
+
+    pub fn custody_log(&self, evidence_id: &str) -> Vec<CustodyEvent> {
+        self.evidence.get(evidence_id)
+            .map(|e| e.chain_of_custody.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_collection(&self, id: &str) -> Option<ForensicCollection> {
+        self.collections.get(id).map(|c| c.clone())
+    }
+
+    pub fn get_evidence(&self, id: &str) -> Option<Evidence> {
+        self.evidence.get(id).map(|e| e.clone())
+    }
+
+    /// Collections opened for a case, oldest first
+    pub fn collections_for_case(&self, case_id: &str) -> Vec<ForensicCollection> {
+        let mut collections: Vec<_> = self.collections.iter()
+            .filter(|c| c.case_id == case_id)
+            .map(|c| c.clone())
+            .collect();
+        collections.sort_by_key(|c| c.created_at);
+        collections
+    }
+
+    async fn store_artifact(
+        &self,
+        collection_id: &str,
+        artifact: Artifact,
+        source: &str,
+        action: CustodyAction,
+        actor: &str,
+        notes: String,
+    ) -> Result<Evidence, ForensicsError> {
+        let hash = sha256_hex(&artifact.content);
+        let storage_path = self.store.put(&hash, &artifact.content).await?;
+
+        let mut metadata = artifact.metadata;
+        metadata.insert("source".to_string(), source.to_string());
+        metadata.insert("content_type".to_string(), artifact.content_type);
+        metadata.insert("captured_at".to_string(), artifact.captured_at.to_rfc3339());
+
+        let mut evidence = Evidence {
+            id: uuid::Uuid::new_v4().to_string(),
+            collection_id: collection_id.to_string(),
+            evidence_type: artifact.evidence_type,
+            source_host: artifact.source_host,
+            hash_sha256: hash,
+            size_bytes: artifact.content.len() as u64,
+            collected_at: chrono::Utc::now(),
+            chain_of_custody: Vec::new(),
+            storage_path,
+            metadata,
+        };
+        custody::append(&mut evidence.chain_of_custody, &evidence.hash_sha256, action, actor, Some(notes));
+
+        self.evidence.insert(evidence.id.clone(), evidence.clone());
+        if let Some(mut c) = self.collections.get_mut(collection_id) {
+            c.evidence_ids.push(evidence.id.clone());
+        }
+        Ok(evidence)
+    }
+
+    fn log_custody(
+        &self,
+        evidence_id: &str,
+        action: CustodyAction,
+        actor: &str,
+        notes: Option<String>,
+    ) -> Result<CustodyEvent, ForensicsError> {
+        let mut evidence = self.evidence.get_mut(evidence_id)
+            .ok_or_else(|| ForensicsError::NotFound(format!("evidence {}", evidence_id)))?;
+        let evidence = evidence.value_mut();
+        Ok(custody::append(&mut evidence.chain_of_custody, &evidence.hash_sha256, action, actor, notes))
+    }
+
+    async fn attach_to_case(&self, case_id: &str, evidence: &[Evidence], actor: &str) {
+        let Some(cases) = &self.cases else { return };
+        if cases.get(case_id).is_none() {
+            return;
+        }
+        for item in evidence {
+            if let Err(e) = cases.attach_evidence(case_id, item, None, actor).await {
+                tracing::warn!("Could not attach evidence {} to case {}: {}", item.id, case_id, e);
+            }
+        }
+    }
+}
+
+impl Default for ForensicsCollector {
+    fn default() -> Self { Self::new() }
+}
+
+#[derive(Debug)]
+pub enum ForensicsError {
+    NotFound(String),
+    Source(String),
+    Storage(String),
+    IntegrityViolation(String),
+}
+
+impl std::fmt::Display for ForensicsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(e) => write!(f, "Not found: {}", e),
+            Self::Source(e) => write!(f, "Source error: {}", e),
+            Self::Storage(e) => write!(f, "Storage error: {}", e),
+            Self::IntegrityViolation(e) => write!(f, "Integrity violation: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ForensicsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct Capture;
+
+    #[async_trait]
+    impl ArtifactSource for Capture {
+        fn name(&self) -> &str {
+            "dataplane"
+        }
+
+        fn evidence_types(&self) -> Vec<EvidenceType> {
+            vec![EvidenceType::NetworkCapture, EvidenceType::FlowSnapshot]
+        }
+
+        async fn collect(&self, evidence_type: EvidenceType, target: &CollectionTarget) -> Result<Vec<Artifact>, ForensicsError> {
+            if evidence_type == EvidenceType::FlowSnapshot {
+                return Err(ForensicsError::Source("flow table unavailable".to_string()));
+            }
+            Ok(vec![Artifact {
+                evidence_type,
+                source_host: "pop-ams-1".to_string(),
+                content: format!("pcap for {}", target.ip.as_deref().unwrap_or_default()).into_bytes(),
+                content_type: "application/vnd.tcpdump.pcap".to_string(),
+                captured_at: chrono::Utc::now(),
+                metadata: HashMap::new(),
+            }])
+        }
+    }
+
+    /// Memory store whose blobs a test can overwrite
+    #[derive(Default)]
+    struct TamperableStore {
+        blobs: dashmap::DashMap<String, Vec<u8>>,
+    }
+
+    #[async_trait]
+    impl EvidenceStore for TamperableStore {
+        async fn put(&self, sha256: &str, content: &[u8]) -> Result<String, ForensicsError> {
+            self.blobs.insert(sha256.to_string(), content.to_vec());
+            Ok(format!("test://{}", sha256))
+        }
+
+        async fn get(&self, sha256: &str) -> Result<Option<Vec<u8>>, ForensicsError> {
+            Ok(self.blobs.get(sha256).map(|b| b.clone()))
+        }
+    }
+
+    fn request(types: Vec<EvidenceType>) -> CollectionRequest {
+        CollectionRequest {
+            evidence_types: types,
+            target: CollectionTarget {
+                ip: Some("203.0.113.7".to_string()),
+                reason: "beaconing".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_records_failures_and_attaches_to_case() {
+        let cases = Arc::new(CaseManager::new());
+        let case = cases.create_case("Beaconing", "C2 traffic", crate::Severity::High, crate::cases::CaseType::Malware, "alice").await;
+        let collector = ForensicsCollector::new().with_case_manager(cases.clone());
+        collector.register_source(Arc::new(Capture));
+
+        let types = vec![EvidenceType::NetworkCapture, EvidenceType::FlowSnapshot, EvidenceType::MemoryDump];
+        let (collection_id, evidence) = collector.collect_for_case(&case.id, "initial", &request(types), "alice").await.unwrap();
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].hash_sha256, sha256_hex(b"pcap for 203.0.113.7"));
+        assert_eq!(evidence[0].metadata["source"], "dataplane");
+
+        let collection = collector.get_collection(&collection_id).unwrap();
+        assert_eq!(collection.status, CollectionStatus::Completed);
+        assert_eq!(collection.failures, vec![
+            ("dataplane".to_string(), "Source error: flow table unavailable".to_string()),
+            ("forensics".to_string(), "No source for MemoryDump".to_string()),
+        ]);
+        assert_eq!(cases.get(&case.id).unwrap().evidence[0].evidence_id, evidence[0].id);
+
+        // Nothing collected and a source failed
+        let (failed, _) = collector.collect_for_case(&case.id, "flows", &request(vec![EvidenceType::FlowSnapshot]), "alice").await.unwrap();
+        assert_eq!(collector.get_collection(&failed).unwrap().status, CollectionStatus::Failed);
+        assert_eq!(collector.collections_for_case(&case.id).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_logged_and_tampering_detected() {
+        let store = Arc::new(TamperableStore::default());
+        let collector = ForensicsCollector::new().with_store(store.clone());
+        collector.register_source(Arc::new(Capture));
+        let collection = collector.create_collection("case-1", "pcaps", "alice").await;
+        let evidence = collector.collect(&collection, &request(vec![EvidenceType::NetworkCapture]), "alice").await.unwrap().remove(0);
+
+        assert_eq!(collector.read_content(&evidence.id, "bob", "triage").await.unwrap(), b"pcap for 203.0.113.7");
+        collector.transfer(&evidence.id, "bob", "legal", None).unwrap();
+        let report = collector.verify(&evidence.id, "bob").await.unwrap();
+        assert!(report.is_intact());
+        let actions: Vec<CustodyAction> = collector.custody_log(&evidence.id).iter().map(|e| e.action).collect();
+        assert_eq!(actions, vec![
+            CustodyAction::Collected,
+            CustodyAction::Accessed,
+            CustodyAction::Transferred,
+            CustodyAction::Verified,
+        ]);
+
+        store.blobs.insert(evidence.hash_sha256.clone(), b"edited".to_vec());
+        assert!(matches!(
+            collector.read_content(&evidence.id, "bob", "review").await,
+            Err(ForensicsError::IntegrityViolation(_))
+        ));
+        let report = collector.verify(&evidence.id, "bob").await.unwrap();
+        assert_eq!((report.content_intact, report.chain_intact), (Some(false), true));
+
+        collector.evidence.get_mut(&evidence.id).unwrap().chain_of_custody[1].actor = "mallory".to_string();
+        let report = collector.verify(&evidence.id, "bob").await.unwrap();
+        assert_eq!(report.broken_at, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_add_evidence_reseals_supplied_custody() {
+        let collector = ForensicsCollector::new();
+        let collection = collector.create_collection("case-1", "edr", "alice").await;
+        let supplied = CustodyEvent {
+            sequence: 7,
+            timestamp: chrono::Utc::now(),
+            action: CustodyAction::Collected,
+            actor: "edr-agent".to_string(),
+            notes: None,
+            prev_hash: "bogus".to_string(),
+            entry_hash: "bogus".to_string(),
+        };
+        collector.add_evidence(&collection, Evidence {
+            id: "ev-1".to_string(),
+            collection_id: collection.clone(),
+            evidence_type: EvidenceType::MemoryDump,
+            source_host: "laptop-7".to_string(),
+            hash_sha256: sha256_hex(b"dump"),
+            size_bytes: 4,
+            collected_at: chrono::Utc::now(),
+            chain_of_custody: vec![supplied],
+            storage_path: "s3://evidence/dump".to_string(),
+            metadata: HashMap::new(),
+        }).await;
+
+        let log = collector.custody_log("ev-1");
+        assert_eq!(log.iter().map(|e| (e.sequence, e.action)).collect::<Vec<_>>(),
+            vec![(0, CustodyAction::Collected), (1, CustodyAction::Registered)]);
+        let report = collector.verify("ev-1", "alice").await.unwrap();
+        // Content is held outside the store
+        assert_eq!((report.content_intact, report.chain_intact), (None, true));
+        assert_eq!(collector.get_collection(&collection).unwrap().evidence_ids, vec!["ev-1"]);
+    }
+}
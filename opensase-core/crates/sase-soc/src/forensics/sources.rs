@@ -0,0 +1,58 @@
+//! Artifact sources
+//!
+//! The SOC crate sits below the data plane, ZTNA, RBI and the email gateway
+//! in the dependency graph, so those services implement [`ArtifactSource`]
+//! and are registered with the [`ForensicsCollector`](super::ForensicsCollector)
+//! at startup.
+
+use super::{EvidenceType, ForensicsError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// What to collect evidence about
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct CollectionTarget {
+    pub tenant_id: Option<String>,
+    pub host: Option<String>,
+    pub ip: Option<String>,
+    pub user: Option<String>,
+    /// Mail message ID, for email samples
+    pub message_id: Option<String>,
+    /// ZTNA or RBI session, for recordings
+    pub session_id: Option<String>,
+    pub alert_id: Option<String>,
+    /// Time window to capture; sources pick a default when unset
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub reason: String,
+}
+
+/// Content handed back by a source
+#[derive(Clone, Debug)]
+pub struct Artifact {
+    pub evidence_type: EvidenceType,
+    /// Host or service the artifact came from
+    pub source_host: String,
+    pub content: Vec<u8>,
+    /// MIME type, e.g. `application/vnd.tcpdump.pcap`
+    pub content_type: String,
+    pub captured_at: DateTime<Utc>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// A service that can produce artifacts (packet captures, flow snapshots,
+/// email samples, session recordings)
+#[async_trait]
+pub trait ArtifactSource: Send + Sync {
+    /// Source name, recorded with each artifact
+    fn name(&self) -> &str;
+    /// Evidence types this source can collect
+    fn evidence_types(&self) -> Vec<EvidenceType>;
+    /// Collect artifacts of one type; an empty result means nothing matched
+    async fn collect(
+        &self,
+        evidence_type: EvidenceType,
+        target: &CollectionTarget,
+    ) -> Result<Vec<Artifact>, ForensicsError>;
+}
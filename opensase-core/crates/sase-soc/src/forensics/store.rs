@@ -0,0 +1,124 @@
+//! Content-addressed evidence storage
+//!
+//! Artifacts are stored under the hex SHA-256 of their content. Identical
+//! captures collected twice share one blob, and a blob whose content no
+//! longer matches its address has been tampered with.
+
+use super::ForensicsError;
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Hex SHA-256 of some content
+pub fn sha256_hex(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// Blob storage keyed by content hash
+#[async_trait]
+pub trait EvidenceStore: Send + Sync {
+    /// Store content under its hash; returns where it lives. Storing content
+    /// that is already present is a no-op.
+    async fn put(&self, sha256: &str, content: &[u8]) -> Result<String, ForensicsError>;
+    /// Content stored under a hash
+    async fn get(&self, sha256: &str) -> Result<Option<Vec<u8>>, ForensicsError>;
+}
+
+/// In-memory store for tests and single-node deployments
+#[derive(Default)]
+pub struct MemoryEvidenceStore {
+    blobs: dashmap::DashMap<String, Vec<u8>>,
+}
+
+impl MemoryEvidenceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl EvidenceStore for MemoryEvidenceStore {
+    async fn put(&self, sha256: &str, content: &[u8]) -> Result<String, ForensicsError> {
+        self.blobs.entry(sha256.to_string()).or_insert_with(|| content.to_vec());
+        Ok(format!("memory://{}", sha256))
+    }
+
+    async fn get(&self, sha256: &str) -> Result<Option<Vec<u8>>, ForensicsError> {
+        Ok(self.blobs.get(sha256).map(|b| b.clone()))
+    }
+}
+
+/// Blobs on disk as `<root>/<aa>/<bb>/<sha256>`
+pub struct FileEvidenceStore {
+    root: PathBuf,
+}
+
+impl FileEvidenceStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, sha256: &str) -> Result<PathBuf, ForensicsError> {
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ForensicsError::Storage(format!("Invalid content hash '{}'", sha256)));
+        }
+        Ok(self.root.join(&sha256[..2]).join(&sha256[2..4]).join(sha256))
+    }
+}
+
+#[async_trait]
+impl EvidenceStore for FileEvidenceStore {
+    async fn put(&self, sha256: &str, content: &[u8]) -> Result<String, ForensicsError> {
+        let path = self.path(sha256)?;
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(path.display().to_string());
+        }
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await
+                .map_err(|e| ForensicsError::Storage(format!("{}: {}", dir.display(), e)))?;
+        }
+
+        // Write aside and rename so a blob is never seen half-written
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        let write = async {
+            tokio::fs::write(&partial, content).await?;
+            tokio::fs::rename(&partial, &path).await
+        };
+        if let Err(e) = write.await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(ForensicsError::Storage(format!("{}: {}", path.display(), e)));
+        }
+        Ok(path.display().to_string())
+    }
+
+    async fn get(&self, sha256: &str) -> Result<Option<Vec<u8>>, ForensicsError> {
+        let path = self.path(sha256)?;
+        match tokio::fs::read(&path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ForensicsError::Storage(format!("{}: {}", path.display(), e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_store_round_trip() {
+        let root = std::env::temp_dir().join(format!("evidence-{}", uuid::Uuid::new_v4()));
+        let store = FileEvidenceStore::new(&root);
+        let hash = sha256_hex(b"capture");
+
+        assert!(store.get(&hash).await.unwrap().is_none());
+        let path = store.put(&hash, b"capture").await.unwrap();
+        assert!(path.ends_with(&format!("{}/{}/{}", &hash[..2], &hash[2..4], hash)));
+        // Already present: the first content stays
+        assert_eq!(store.put(&hash, b"other").await.unwrap(), path);
+        assert_eq!(store.get(&hash).await.unwrap().as_deref(), Some(&b"capture"[..]));
+
+        assert!(matches!(store.put("../../etc/passwd", b"x").await, Err(ForensicsError::Storage(_))));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,867 @@
+//! Threat Hunting
+//!
+//! Proactive threat detection.
+//!
+//! Besides indicator matching, the hunter hosts saved hunt notebooks:
+//! parameterized query → transform → visualization pipelines that can be
+//! run on demand or on a schedule, shared within a tenant's SOC team.
+//!
+//! Every ingested event is also written to an [`EventStore`] so analysts can
+//! search history with the hunt query language, either ad hoc or as saved
+//! hunts that run on a schedule and raise alerts on hits.
+
+mod query;
+mod saved;
+mod store;
+
+pub use query::{
+    AggFunc, Aggregation, CmpOp, Expr, HuntQuery, HuntResult, IntelHit, IntelLookup, IntelRecord,
+    NoIntel, Stage, TextOp,
+};
+pub use saved::{HuntAlertConfig, HuntRun, HuntSchedule, SavedHunt};
+pub use store::{EventStore, StoredEvent};
+
+use crate::{Indicator, IndicatorType, SecurityEvent, ThreatIntelMatch};
+use crate::siem::{SiemIntegration, TimeRange};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+pub struct ThreatHunter {
+    feeds: dashmap::DashMap<String, ThreatFeed>,
+    indicator_cache: dashmap::DashMap<String, CachedIndicator>,
+    queries: dashmap::DashMap<String, HuntingQuery>,
+    /// Saved notebooks by id
+    notebooks: dashmap::DashMap<String, HuntNotebook>,
+    /// Run snapshots per notebook, newest last
+    snapshots: dashmap::DashMap<String, VecDeque<NotebookRun>>,
+    /// Snapshots retained per notebook, and runs per saved hunt
+    max_snapshots: usize,
+    /// Historical events for hunt queries
+    events: EventStore,
+    /// Saved hunts by id
+    hunts: dashmap::DashMap<String, SavedHunt>,
+    /// Runs per saved hunt, newest last
+    hunt_runs: dashmap::DashMap<String, VecDeque<HuntRun>>,
+}
+
+#[derive(Clone)]
+pub struct ThreatFeed {
+    pub id: String,
+    pub name: String,
+    pub feed_type: FeedType,
+    pub enabled: bool,
+    pub last_update: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FeedType { OpenSource, Commercial, Internal }
+
+#[derive(Clone)]
+pub struct CachedIndicator {
+    pub value: String,
+    pub indicator_type: IndicatorType,
+    pub feeds: Vec<String>,
+    pub confidence: f64,
+}
+
+#[derive(Clone)]
+pub struct HuntingQuery {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    pub mitre_attack: Vec<String>,
+    pub enabled: bool,
+}
+
+impl ThreatHunter {
+    pub fn new() -> Self {
+        Self {
+            feeds: dashmap::DashMap::new(),
+            indicator_cache: dashmap::DashMap::new(),
+            queries: dashmap::DashMap::new(),
+            notebooks: dashmap::DashMap::new(),
+            snapshots: dashmap::DashMap::new(),
+            max_snapshots: 50,
+            events: EventStore::new(),
+            hunts: dashmap::DashMap::new(),
+            hunt_runs: dashmap::DashMap::new(),
+        }
+    }
+    
+    /// Use a differently sized event store
+    pub fn with_event_store(mut self, events: EventStore) -> Self {
+        self.events = events;
+        self
+    }
+    
+    /// Number of run snapshots kept per notebook
+    pub fn with_max_snapshots(mut self, max: usize) -> Self {
+        self.max_snapshots = max.max(1);
+        self
+    }
+    
+    pub async fn check_indicator(&self, indicator: &Indicator) -> Option<ThreatIntelMatch> {
+        if let Some(cached) = self.indicator_cache.get(&indicator.value) {
+            return Some(ThreatIntelMatch {
+                feed: cached.feeds.first().cloned().unwrap_or_default(),
+                indicator: indicator.value.clone(),
+                threat_type: "malicious".to_string(),
+                confidence: cached.confidence,
+            });
+        }
+        None
+    }
+    
+    pub fn add_indicator(&self, indicator: CachedIndicator) {
+        self.indicator_cache.insert(indicator.value.clone(), indicator);
+    }
+    
+    pub fn register_query(&self, query: HuntingQuery) {
+        self.queries.insert(query.id.clone(), query);
+    }
+    
+    /// Write path for the event store; events without a tenant are filed
+    /// under `default_tenant`
+    pub fn record_event(&self, event: &SecurityEvent, default_tenant: &str) {
+        self.events.append(event, default_tenant);
+    }
+    
+    pub fn event_store(&self) -> &EventStore {
+        &self.events
+    }
+    
+    /// Run an ad hoc query over the principal's tenant
+    pub fn hunt(&self, principal: &HuntPrincipal, query: &str, time_range: TimeRange) -> Result<HuntResult, HuntError> {
+        let query = HuntQuery::parse(query)?;
+        Ok(self.events.search(&query, &time_range, Some(&principal.tenant_id), self))
+    }
+}
+
+impl IntelLookup for ThreatHunter {
+    fn lookup(&self, value: &str) -> Option<IntelRecord> {
+        self.indicator_cache.get(value).map(|cached| IntelRecord {
+            indicator_type: cached.indicator_type,
+            feeds: cached.feeds.clone(),
+            confidence: cached.confidence,
+        })
+    }
+}
+
+impl Default for ThreatHunter {
+    fn default() -> Self { Self::new() }
+}
+
+// =============================================================================
+// Hunt Notebooks
+// =============================================================================
+
+/// Executes the query steps of a notebook
+#[async_trait]
+pub trait HuntQueryBackend: Send + Sync {
+    async fn run_query(&self, source: &str, query: &str, time_range: TimeRange) -> Result<Vec<Value>, HuntError>;
+}
+
+#[async_trait]
+impl HuntQueryBackend for SiemIntegration {
+    async fn run_query(&self, source: &str, query: &str, time_range: TimeRange) -> Result<Vec<Value>, HuntError> {
+        self.query(source, query, time_range).await
+            .map_err(|e| HuntError::QueryFailed(e.to_string()))
+    }
+}
+
+/// Saved, parameterized analysis
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HuntNotebook {
+    pub id: String,
+    pub tenant_id: String,
+    pub name: String,
+    pub description: String,
+    pub owner: String,
+    pub parameters: Vec<NotebookParameter>,
+    pub steps: Vec<NotebookStep>,
+    pub schedule: Option<NotebookSchedule>,
+    pub sharing: NotebookSharing,
+    pub mitre_attack: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotebookParameter {
+    pub name: String,
+    pub param_type: ParameterType,
+    pub default: Option<Value>,
+    pub required: bool,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterType { String, Integer, Float, Boolean, Timestamp }
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotebookStep {
+    pub id: String,
+    pub name: String,
+    pub kind: StepKind,
+}
+
+/// Step body. `input` names an earlier step; `None` means the previous one.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepKind {
+    Query { source: String, query: String, lookback_minutes: i64 },
+    Transform { input: Option<String>, ops: Vec<TransformOp> },
+    Visualization { input: Option<String>, spec: VisualizationSpec },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransformOp {
+    Filter { field: String, compare: CompareOp, value: Value },
+    Project { fields: Vec<String> },
+    CountBy { field: String },
+    Sort { field: String, descending: bool },
+    Limit { count: usize },
+    Dedup { field: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp { Eq, Ne, Gt, Gte, Lt, Lte, Contains, Matches }
+
+/// Rendering hints passed through to the UI alongside the step's rows
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct VisualizationSpec {
+    pub chart: ChartType,
+    pub title: String,
+    pub x: Option<String>,
+    pub y: Option<String>,
+    pub series: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartType { Table, Bar, Line, Pie, Timeline }
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotebookSchedule {
+    pub interval_secs: u64,
+    /// Parameter values used for scheduled runs
+    pub parameters: HashMap<String, Value>,
+    pub enabled: bool,
+    pub next_run: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Access levels, ordered from least to most privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotebookAccess { View, Run, Edit, Owner }
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct NotebookSharing {
+    /// Access granted to every analyst in the owning tenant
+    pub team: Option<NotebookAccess>,
+    /// Per-analyst grants; capped at `Edit`
+    pub users: HashMap<String, NotebookAccess>,
+}
+
+/// Analyst acting on a notebook
+#[derive(Debug, Clone)]
+pub struct HuntPrincipal {
+    pub user_id: String,
+    pub tenant_id: String,
+}
+
+/// Result snapshot of one notebook execution
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotebookRun {
+    pub id: String,
+    pub notebook_id: String,
+    pub tenant_id: String,
+    pub trigger: RunTrigger,
+    pub parameters: HashMap<String, Value>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub status: RunStatus,
+    pub steps: Vec<StepResult>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunTrigger {
+    Manual { user_id: String },
+    Schedule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus { Succeeded, Failed }
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StepResult {
+    pub step_id: String,
+    pub rows: Vec<Value>,
+    pub visualization: Option<VisualizationSpec>,
+    pub duration_ms: u64,
+}
+
+impl HuntNotebook {
+    pub fn new(name: &str) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            tenant_id: String::new(),
+            name: name.to_string(),
+            description: String::new(),
+            owner: String::new(),
+            parameters: vec![],
+            steps: vec![],
+            schedule: None,
+            sharing: NotebookSharing::default(),
+            mitre_attack: vec![],
+            created_at: now,
+            updated_at: now,
+        }
+    }
+    
+    /// Effective access of `principal`, `None` if it may not see the notebook
+    pub fn access_for(&self, principal: &HuntPrincipal) -> Option<NotebookAccess> {
+        if principal.tenant_id != self.tenant_id {
+            return None;
+        }
+        if principal.user_id == self.owner {
+            return Some(NotebookAccess::Owner);
+        }
+        let user = self.sharing.users.get(&principal.user_id)
+            .map(|a| (*a).min(NotebookAccess::Edit));
+        let team = self.sharing.team.map(|a| a.min(NotebookAccess::Edit));
+        user.max(team)
+    }
+    
+    /// Structural checks: unique step ids, inputs refer to earlier steps,
+    /// every placeholder is a declared parameter
+    pub fn validate(&self) -> Result<(), HuntError> {
+        let declared: HashSet<&str> = self.parameters.iter().map(|p| p.name.as_str()).collect();
+        let mut seen: HashSet<&str> = HashSet::new();
+        
+        for (i, step) in self.steps.iter().enumerate() {
+            if !seen.insert(step.id.as_str()) {
+                return Err(HuntError::InvalidNotebook(format!("duplicate step id '{}'", step.id)));
+            }
+            
+            let (input, templates) = match &step.kind {
+                StepKind::Query { query, .. } => (None, vec![query.as_str()]),
+                StepKind::Transform { input, ops } => {
+                    let values = ops.iter().filter_map(|op| match op {
+                        TransformOp::Filter { value: Value::String(s), .. } => Some(s.as_str()),
+                        _ => None,
+                    }).collect();
+                    (input.as_deref(), values)
+                }
+                StepKind::Visualization { input, .. } => (input.as_deref(), vec![]),
+            };
+            
+            if !matches!(step.kind, StepKind::Query { .. }) {
+                match input {
+                    Some(id) if !seen.contains(id) || id == step.id => {
+                        return Err(HuntError::InvalidNotebook(format!(
+                            "step '{}' reads '{}' which is not an earlier step", step.id, id)));
+                    }
+                    None if i == 0 => {
+                        return Err(HuntError::InvalidNotebook(format!(
+                            "step '{}' has no input", step.id)));
+                    }
+                    _ => {}
+                }
+            }
+            
+            for template in templates {
+                for name in placeholders(template) {
+                    if !declared.contains(name) {
+                        return Err(HuntError::InvalidParameter(format!("'{}' is not declared", name)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Merge supplied values with defaults and check types
+    pub fn resolve_parameters(&self, supplied: &HashMap<String, Value>) -> Result<HashMap<String, Value>, HuntError> {
+        if let Some(unknown) = supplied.keys().find(|k| !self.parameters.iter().any(|p| &p.name == *k)) {
+            return Err(HuntError::InvalidParameter(format!("'{}' is not declared", unknown)));
+        }
+        
+        let mut resolved = HashMap::new();
+        for param in &self.parameters {
+            let value = match supplied.get(&param.name).or(param.default.as_ref()) {
+                Some(v) => v.clone(),
+                None if param.required => return Err(HuntError::MissingParameter(param.name.clone())),
+                None => continue,
+            };
+            
+            let ok = match param.param_type {
+                ParameterType::String => value.is_string(),
+                ParameterType::Integer => value.is_i64() || value.is_u64(),
+                ParameterType::Float => value.is_number(),
+                ParameterType::Boolean => value.is_boolean(),
+                ParameterType::Timestamp => value.as_str()
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(s).is_ok())
+                    .unwrap_or(false),
+            };
+            if !ok {
+                return Err(HuntError::InvalidParameter(format!(
+                    "'{}' expects {:?}, got {}", param.name, param.param_type, value)));
+            }
+            resolved.insert(param.name.clone(), value);
+        }
+        Ok(resolved)
+    }
+}
+
+impl ThreatHunter {
+    /// Save a new notebook owned by `principal`
+    pub fn create_notebook(&self, principal: &HuntPrincipal, mut notebook: HuntNotebook) -> Result<String, HuntError> {
+        notebook.tenant_id = principal.tenant_id.clone();
+        notebook.owner = principal.user_id.clone();
+        if notebook.id.is_empty() || self.notebooks.contains_key(&notebook.id) {
+            notebook.id = uuid::Uuid::new_v4().to_string();
+        }
+        notebook.created_at = chrono::Utc::now();
+        notebook.updated_at = notebook.created_at;
+        if let Some(schedule) = notebook.schedule.as_mut() {
+            arm_schedule(schedule);
+        }
+        notebook.validate()?;
+        
+        let id = notebook.id.clone();
+        tracing::info!("Hunt notebook {} created by {}", id, principal.user_id);
+        self.notebooks.insert(id.clone(), notebook);
+        Ok(id)
+    }
+    
+    pub fn get_notebook(&self, principal: &HuntPrincipal, id: &str) -> Result<HuntNotebook, HuntError> {
+        self.authorize(principal, id, NotebookAccess::View)
+    }
+    
+    /// Notebooks visible to `principal`
+    pub fn list_notebooks(&self, principal: &HuntPrincipal) -> Vec<HuntNotebook> {
+        self.notebooks.iter()
+            .filter(|n| n.access_for(principal).is_some())
+            .map(|n| n.clone())
+            .collect()
+    }
+    
+    /// Replace name, description, parameters, steps and schedule.
+    /// Ownership and sharing are kept; use `share_notebook` for those.
+    pub fn update_notebook(&self, principal: &HuntPrincipal, notebook: HuntNotebook) -> Result<(), HuntError> {
+        let current = self.authorize(principal, &notebook.id, NotebookAccess::Edit)?;
+        
+        let mut updated = HuntNotebook {
+            tenant_id: current.tenant_id,
+            owner: current.owner,
+            sharing: current.sharing,
+            created_at: current.created_at,
+            updated_at: chrono::Utc::now(),
+            ..notebook
+        };
+        if let Some(schedule) = updated.schedule.as_mut() {
+            arm_schedule(schedule);
+        }
+        updated.validate()?;
+        
+        self.notebooks.insert(updated.id.clone(), updated);
+        Ok(())
+    }
+    
+    pub fn share_notebook(&self, principal: &HuntPrincipal, id: &str, sharing: NotebookSharing) -> Result<(), HuntError> {
+        self.authorize(principal, id, NotebookAccess::Owner)?;
+        if let Some(mut notebook) = self.notebooks.get_mut(id) {
+            notebook.sharing = sharing;
+            notebook.updated_at = chrono::Utc::now();
+        }
+        Ok(())
+    }
+    
+    pub fn delete_notebook(&self, principal: &HuntPrincipal, id: &str) -> Result<(), HuntError> {
+        self.authorize(principal, id, NotebookAccess::Owner)?;
+        self.notebooks.remove(id);
+        self.snapshots.remove(id);
+        Ok(())
+    }
+    
+    /// Stored run snapshots, oldest first
+    pub fn snapshots(&self, principal: &HuntPrincipal, id: &str) -> Result<Vec<NotebookRun>, HuntError> {
+        self.authorize(principal, id, NotebookAccess::View)?;
+        Ok(self.snapshots.get(id).map(|s| s.iter().cloned().collect()).unwrap_or_default())
+    }
+    
+    /// Run a notebook on demand and keep the snapshot
+    pub async fn run_notebook(
+        &self,
+        principal: &HuntPrincipal,
+        id: &str,
+        parameters: &HashMap<String, Value>,
+        backend: &dyn HuntQueryBackend,
+    ) -> Result<NotebookRun, HuntError> {
+        let notebook = self.authorize(principal, id, NotebookAccess::Run)?;
+        let resolved = notebook.resolve_parameters(parameters)?;
+        
+        let trigger = RunTrigger::Manual { user_id: principal.user_id.clone() };
+        let run = execute_notebook(&notebook, resolved, trigger, backend).await;
+        self.store_snapshot(run.clone());
+        Ok(run)
+    }
+    
+    /// Execute every scheduled notebook whose next run is due
+    pub async fn run_due(&self, backend: &dyn HuntQueryBackend) -> Vec<NotebookRun> {
+        let now = chrono::Utc::now();
+        let mut due = Vec::new();
+        
+        for mut entry in self.notebooks.iter_mut() {
+            let Some(schedule) = entry.schedule.as_mut() else { continue };
+            if !schedule.enabled || schedule.next_run.map(|t| t > now).unwrap_or(false) {
+                continue;
+            }
+            let parameters = schedule.parameters.clone();
+            schedule.next_run = Some(now + interval_of(schedule));
+            due.push((entry.clone(), parameters));
+        }
+        
+        let mut runs = Vec::with_capacity(due.len());
+        for (notebook, parameters) in due {
+            let run = match notebook.resolve_parameters(&parameters) {
+                Ok(resolved) => execute_notebook(&notebook, resolved, RunTrigger::Schedule, backend).await,
+                Err(e) => {
+                    tracing::warn!("Scheduled hunt {} has invalid parameters: {}", notebook.id, e);
+                    failed_run(&notebook, parameters, RunTrigger::Schedule, now, e.to_string())
+                }
+            };
+            self.store_snapshot(run.clone());
+            runs.push(run);
+        }
+        runs
+    }
+    
+    /// Drive scheduled notebooks in the background
+    pub fn spawn_scheduler(
+        self: Arc<Self>,
+        backend: Arc<dyn HuntQueryBackend>,
+        tick: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            loop {
+                interval.tick().await;
+                let runs = self.run_due(backend.as_ref()).await;
+                if !runs.is_empty() {
+                    tracing::debug!("Executed {} scheduled hunts", runs.len());
+                }
+            }
+        })
+    }
+    
+    fn authorize(&self, principal: &HuntPrincipal, id: &str, required: NotebookAccess) -> Result<HuntNotebook, HuntError> {
+        let notebook = self.notebooks.get(id)
+            .map(|n| n.clone())
+            .ok_or_else(|| HuntError::NotFound(id.to_string()))?;
+        
+        // Notebooks outside the analyst's reach are reported as missing
+        match notebook.access_for(principal) {
+            None => Err(HuntError::NotFound(id.to_string())),
+            Some(access) if access < required => Err(HuntError::Forbidden(format!(
+                "{} needs {:?} access to {}", principal.user_id, required, id))),
+            Some(_) => Ok(notebook),
+        }
+    }
+    
+    fn store_snapshot(&self, run: NotebookRun) {
+        let mut history = self.snapshots.entry(run.notebook_id.clone()).or_default();
+        history.push_back(run);
+        while history.len() > self.max_snapshots {
+            history.pop_front();
+        }
+    }
+}
+
+fn arm_schedule(schedule: &mut NotebookSchedule) {
+    if schedule.next_run.is_none() {
+        schedule.next_run = Some(chrono::Utc::now() + interval_of(schedule));
+    }
+}
+
+fn interval_of(schedule: &NotebookSchedule) -> chrono::Duration {
+    chrono::Duration::seconds(schedule.interval_secs.max(60) as i64)
+}
+
+fn failed_run(
+    notebook: &HuntNotebook,
+    parameters: HashMap<String, Value>,
+    trigger: RunTrigger,
+    started_at: chrono::DateTime<chrono::Utc>,
+    error: String,
+) -> NotebookRun {
+    NotebookRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        notebook_id: notebook.id.clone(),
+        tenant_id: notebook.tenant_id.clone(),
+        trigger,
+        parameters,
+        started_at,
+        finished_at: chrono::Utc::now(),
+        status: RunStatus::Failed,
+        steps: vec![],
+        error: Some(error),
+    }
+}
+
+async fn execute_notebook(
+    notebook: &HuntNotebook,
+    parameters: HashMap<String, Value>,
+    trigger: RunTrigger,
+    backend: &dyn HuntQueryBackend,
+) -> NotebookRun {
+    let started_at = chrono::Utc::now();
+    let mut results: Vec<StepResult> = Vec::with_capacity(notebook.steps.len());
+    let mut error = None;
+    
+    for step in &notebook.steps {
+        let start = std::time::Instant::now();
+        match execute_step(step, &results, &parameters, backend).await {
+            Ok((rows, visualization)) => results.push(StepResult {
+                step_id: step.id.clone(),
+                rows,
+                visualization,
+                duration_ms: start.elapsed().as_millis() as u64,
+            }),
+            Err(e) => {
+                error = Some(format!("step '{}': {}", step.id, e));
+                break;
+            }
+        }
+    }
+    
+    NotebookRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        notebook_id: notebook.id.clone(),
+        tenant_id: notebook.tenant_id.clone(),
+        trigger,
+        parameters,
+        started_at,
+        finished_at: chrono::Utc::now(),
+        status: if error.is_some() { RunStatus::Failed } else { RunStatus::Succeeded },
+        steps: results,
+        error,
+    }
+}
+
+async fn execute_step(
+    step: &NotebookStep,
+    previous: &[StepResult],
+    parameters: &HashMap<String, Value>,
+    backend: &dyn HuntQueryBackend,
+) -> Result<(Vec<Value>, Option<VisualizationSpec>), HuntError> {
+    let input_rows = |input: &Option<String>| -> Result<Vec<Value>, HuntError> {
+        let found = match input {
+            Some(id) => previous.iter().find(|r| &r.step_id == id),
+            None => previous.last(),
+        };
+        found.map(|r| r.rows.clone())
+            .ok_or_else(|| HuntError::InvalidNotebook(format!("missing input for '{}'", step.id)))
+    };
+    
+    match &step.kind {
+        StepKind::Query { source, query, lookback_minutes } => {
+            let query = substitute(query, parameters, true)?;
+            let end = chrono::Utc::now();
+            let time_range = TimeRange {
+                start: end - chrono::Duration::minutes((*lookback_minutes).max(1)),
+                end,
+            };
+            let rows = backend.run_query(source, &query, time_range).await?;
+            Ok((rows, None))
+        }
+        StepKind::Transform { input, ops } => {
+            let mut rows = input_rows(input)?;
+            for op in ops {
+                rows = apply_transform(rows, op, parameters)?;
+            }
+            Ok((rows, None))
+        }
+        StepKind::Visualization { input, spec } => {
+            Ok((input_rows(input)?, Some(spec.clone())))
+        }
+    }
+}
+
+fn apply_transform(rows: Vec<Value>, op: &TransformOp, parameters: &HashMap<String, Value>) -> Result<Vec<Value>, HuntError> {
+    Ok(match op {
+        TransformOp::Filter { field, compare, value } => {
+            let value = substitute_value(value, parameters)?;
+            let pattern = match compare {
+                CompareOp::Matches => Some(regex::Regex::new(value.as_str().unwrap_or_default())
+                    .map_err(|e| HuntError::InvalidNotebook(e.to_string()))?),
+                _ => None,
+            };
+            rows.into_iter()
+                .filter(|row| {
+                    let Some(actual) = field_value(row, field) else { return *compare == CompareOp::Ne };
+                    match compare {
+                        CompareOp::Eq => compare_values(actual, &value) == Some(std::cmp::Ordering::Equal),
+                        CompareOp::Ne => compare_values(actual, &value) != Some(std::cmp::Ordering::Equal),
+                        CompareOp::Gt => compare_values(actual, &value) == Some(std::cmp::Ordering::Greater),
+                        CompareOp::Gte => matches!(compare_values(actual, &value),
+                            Some(std::cmp::Ordering::Greater | std::cmp::Ordering::Equal)),
+                        CompareOp::Lt => compare_values(actual, &value) == Some(std::cmp::Ordering::Less),
+                        CompareOp::Lte => matches!(compare_values(actual, &value),
+                            Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)),
+                        CompareOp::Contains => match (actual, &value) {
+                            (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+                            (Value::Array(items), v) => items.contains(v),
+                            _ => false,
+                        },
+                        CompareOp::Matches => match (actual, &pattern) {
+                            (Value::String(a), Some(re)) => re.is_match(a),
+                            _ => false,
+                        },
+                    }
+                })
+                .collect()
+        }
+        TransformOp::Project { fields } => rows.iter()
+            .map(|row| {
+                let projected = fields.iter()
+                    .map(|f| (f.clone(), field_value(row, f).cloned().unwrap_or(Value::Null)))
+                    .collect();
+                Value::Object(projected)
+            })
+            .collect(),
+        TransformOp::CountBy { field } => {
+            let mut counts: Vec<(Value, u64)> = Vec::new();
+            for row in &rows {
+                let key = field_value(row, field).cloned().unwrap_or(Value::Null);
+                match counts.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((key, 1)),
+                }
+            }
+            counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+            counts.into_iter()
+                .map(|(key, count)| serde_json::json!({ field.as_str(): key, "count": count }))
+                .collect()
+        }
+        TransformOp::Sort { field, descending } => {
+            let mut rows = rows;
+            rows.sort_by(|a, b| {
+                let ord = match (field_value(a, field), field_value(b, field)) {
+                    (Some(x), Some(y)) => compare_values(x, y).unwrap_or(std::cmp::Ordering::Equal),
+                    (Some(_), None) => std::cmp::Ordering::Greater,
+                    (None, Some(_)) => std::cmp::Ordering::Less,
+                    (None, None) => std::cmp::Ordering::Equal,
+                };
+                if *descending { ord.reverse() } else { ord }
+            });
+            rows
+        }
+        TransformOp::Limit { count } => rows.into_iter().take(*count).collect(),
+        TransformOp::Dedup { field } => {
+            let mut seen = HashSet::new();
+            rows.into_iter()
+                .filter(|row| seen.insert(field_value(row, field).map(|v| v.to_string()).unwrap_or_default()))
+                .collect()
+        }
+    })
+}
+
+/// Dotted path lookup, e.g. `source.ip`
+fn field_value<'a>(row: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(row, |v, key| v.get(key))
+}
+
+fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Null, Value::Null) => Some(std::cmp::Ordering::Equal),
+        _ => (a == b).then_some(std::cmp::Ordering::Equal),
+    }
+}
+
+/// Names of `{{param}}` placeholders in a template
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        names.push(rest[start + 2..start + 2 + len].trim());
+        rest = &rest[start + 2 + len + 2..];
+    }
+    names
+}
+
+/// Replace `{{param}}` placeholders. With `quote` set, string values are
+/// escaped so they cannot break out of a quoted literal in the query.
+fn substitute(template: &str, parameters: &HashMap<String, Value>, quote: bool) -> Result<String, HuntError> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = parameters.get(name)
+            .ok_or_else(|| HuntError::MissingParameter(name.to_string()))?;
+        
+        out.push_str(&rest[..start]);
+        match value {
+            Value::String(s) if quote => out.push_str(&s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Value::String(s) => out.push_str(s),
+            other => out.push_str(&other.to_string()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A filter value that is exactly one placeholder takes the parameter's
+/// type; otherwise placeholders are interpolated into the string.
+fn substitute_value(value: &Value, parameters: &HashMap<String, Value>) -> Result<Value, HuntError> {
+    let Value::String(s) = value else { return Ok(value.clone()) };
+    let names = placeholders(s);
+    if names.len() == 1 && s.trim().starts_with("{{") && s.trim().ends_with("}}") {
+        return parameters.get(names[0]).cloned()
+            .ok_or_else(|| HuntError::MissingParameter(names[0].to_string()));
+    }
+    Ok(Value::String(substitute(s, parameters, false)?))
+}
+
+#[derive(Debug)]
+pub enum HuntError {
+    NotFound(String),
+    Forbidden(String),
+    InvalidNotebook(String),
+    MissingParameter(String),
+    InvalidParameter(String),
+    QueryFailed(String),
+    InvalidQuery(String),
+}
+
+impl std::fmt::Display for HuntError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(e) => write!(f, "Not found: {}", e),
+            Self::Forbidden(e) => write!(f, "Forbidden: {}", e),
+            Self::InvalidNotebook(e) => write!(f, "Invalid notebook: {}", e),
+            Self::MissingParameter(e) => write!(f, "Missing parameter: {}", e),
+            Self::InvalidParameter(e) => write!(f, "Invalid parameter: {}", e),
+            Self::QueryFailed(e) => write!(f, "Query failed: {}", e),
+            Self::InvalidQuery(e) => write!(f, "Invalid query: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HuntError {}
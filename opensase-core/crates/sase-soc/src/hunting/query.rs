@@ -0,0 +1,1063 @@
+//! Hunting query language
+//!
+//! A query is a filter over stored events followed by piped stages:
+//!
+//! ```text
+//! event_type = BruteForceAttempt and severity >= high and source.ip exists
+//!   | intel source.ip as ti
+//!   | stats count() as attempts, dc(raw_data.user) as users by source.ip
+//!   | where attempts > 20
+//!   | sort attempts desc
+//!   | limit 10
+//! ```
+//!
+//! Filters support `=`, `!=`, `>`, `>=`, `<`, `<=`, regex matches (`=~` and
+//! `!~` with `/pattern/flags` or a quoted pattern), `contains`, `startswith`,
+//! `endswith`, `in (...)` and `exists`, combined with `and`, `or`, `not` and
+//! parentheses. Fields are dotted paths; arrays match if any element does,
+//! so `indicators.value = "1.2.3.4"` checks every indicator. String
+//! comparisons ignore case, and severity names compare by rank.
+//!
+//! Stages:
+//!
+//! - `where <filter>`
+//! - `stats <agg>[ as name], ... [by field, ...]` with `count()`,
+//!   `count(f)`, `dc(f)`, `sum(f)`, `avg(f)`, `min(f)`, `max(f)`, `values(f)`
+//! - `sort field [asc|desc], ...`
+//! - `limit n`
+//! - `fields f, ...`
+//! - `dedup f, ...`
+//! - `intel field [as name]`: keep rows where the field matches a known
+//!   threat indicator, attaching the indicator under `name` (default `intel`)
+
+use super::store::severity_rank;
+use super::HuntError;
+use crate::IndicatorType;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Event ids remembered per result row
+const MAX_ROW_EVENTS: usize = 1_000;
+/// Distinct items kept by `values()`
+const MAX_AGG_VALUES: usize = 100;
+/// Compiled size limit for query regexes
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// =============================================================================
+// Syntax Tree
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct HuntQuery {
+    pub text: String,
+    pub filter: Option<Expr>,
+    pub stages: Vec<Stage>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare { field: String, op: CmpOp, value: Value },
+    Text { field: String, op: TextOp, value: String },
+    Regex { field: String, pattern: regex::Regex, negate: bool },
+    In { field: String, values: Vec<Value> },
+    Exists(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp { Eq, Ne, Gt, Gte, Lt, Lte }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextOp { Contains, StartsWith, EndsWith }
+
+#[derive(Debug, Clone)]
+pub enum Stage {
+    Where(Expr),
+    Stats { aggs: Vec<Aggregation>, by: Vec<String> },
+    Sort(Vec<(String, bool)>),
+    Limit(usize),
+    Fields(Vec<String>),
+    Dedup(Vec<String>),
+    Intel { field: String, alias: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Aggregation {
+    pub func: AggFunc,
+    pub field: Option<String>,
+    pub alias: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggFunc { Count, DistinctCount, Sum, Avg, Min, Max, Values }
+
+// =============================================================================
+// Results
+// =============================================================================
+
+/// Threat intel known for an indicator value
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IntelRecord {
+    pub indicator_type: IndicatorType,
+    pub feeds: Vec<String>,
+    pub confidence: f64,
+}
+
+/// Source of threat intel for `intel` stages
+pub trait IntelLookup {
+    fn lookup(&self, value: &str) -> Option<IntelRecord>;
+}
+
+/// Lookup that knows no indicators
+pub struct NoIntel;
+
+impl IntelLookup for NoIntel {
+    fn lookup(&self, _value: &str) -> Option<IntelRecord> {
+        None
+    }
+}
+
+/// Indicator matched by an `intel` stage
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IntelHit {
+    pub field: String,
+    pub value: String,
+    pub indicator_type: IndicatorType,
+    pub feeds: Vec<String>,
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HuntResult {
+    pub query: String,
+    pub rows: Vec<Value>,
+    /// Events behind the returned rows
+    pub event_ids: Vec<String>,
+    pub intel_hits: Vec<IntelHit>,
+    /// Events in range that were examined
+    pub scanned: usize,
+    /// Events that passed the leading filter
+    pub matched: usize,
+    pub duration_ms: u64,
+}
+
+impl HuntResult {
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+struct Row {
+    value: Value,
+    events: Vec<String>,
+}
+
+// =============================================================================
+// Evaluation
+// =============================================================================
+
+impl HuntQuery {
+    pub fn parse(text: &str) -> Result<Self, HuntError> {
+        let mut parser = Parser { tokens: lex(text)?, pos: 0 };
+
+        let filter = match parser.peek() {
+            None | Some(Token::Pipe) => None,
+            Some(_) => Some(parser.expr()?),
+        };
+        let mut stages = Vec::new();
+        while let Some(token) = parser.advance() {
+            match token {
+                Token::Pipe => stages.push(parser.stage()?),
+                other => return Err(syntax(format!("unexpected {}", other))),
+            }
+        }
+
+        Ok(Self { text: text.trim().to_string(), filter, stages })
+    }
+
+    /// Whether the query aggregates, so rows are no longer single events
+    pub fn is_aggregate(&self) -> bool {
+        self.stages.iter().any(|s| matches!(s, Stage::Stats { .. }))
+    }
+
+    pub fn matches(&self, doc: &Value) -> bool {
+        match &self.filter {
+            Some(filter) => filter.eval(doc),
+            None => true,
+        }
+    }
+
+    /// Run over `(event id, event document)` pairs
+    pub fn execute<'a>(
+        &self,
+        events: impl Iterator<Item = (&'a str, &'a Value)>,
+        intel: &dyn IntelLookup,
+    ) -> HuntResult {
+        let started = std::time::Instant::now();
+        let mut scanned = 0;
+        let mut rows = Vec::new();
+        for (id, doc) in events {
+            scanned += 1;
+            if self.matches(doc) {
+                rows.push(Row { value: doc.clone(), events: vec![id.to_string()] });
+            }
+        }
+        let matched = rows.len();
+
+        let mut intel_hits: Vec<IntelHit> = Vec::new();
+        for stage in &self.stages {
+            rows = apply_stage(stage, rows, intel, &mut intel_hits);
+        }
+
+        let mut seen = HashSet::new();
+        let event_ids = rows.iter()
+            .flat_map(|r| r.events.iter())
+            .filter(|id| seen.insert(id.as_str()))
+            .cloned()
+            .collect();
+
+        HuntResult {
+            query: self.text.clone(),
+            rows: rows.into_iter().map(|r| r.value).collect(),
+            event_ids,
+            intel_hits,
+            scanned,
+            matched,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, doc: &Value) -> bool {
+        match self {
+            Self::And(a, b) => a.eval(doc) && b.eval(doc),
+            Self::Or(a, b) => a.eval(doc) || b.eval(doc),
+            Self::Not(e) => !e.eval(doc),
+            Self::Compare { field, op, value } => {
+                let values = lookup_all(doc, field);
+                match op {
+                    CmpOp::Eq => values.iter().any(|v| compare(v, value) == Some(Ordering::Equal)),
+                    CmpOp::Ne => !values.iter().any(|v| compare(v, value) == Some(Ordering::Equal)),
+                    CmpOp::Gt => values.iter().any(|v| compare(v, value) == Some(Ordering::Greater)),
+                    CmpOp::Gte => values.iter()
+                        .any(|v| matches!(compare(v, value), Some(Ordering::Greater | Ordering::Equal))),
+                    CmpOp::Lt => values.iter().any(|v| compare(v, value) == Some(Ordering::Less)),
+                    CmpOp::Lte => values.iter()
+                        .any(|v| matches!(compare(v, value), Some(Ordering::Less | Ordering::Equal))),
+                }
+            }
+            Self::Text { field, op, value } => lookup_all(doc, field).iter().any(|v| {
+                let Some(text) = as_text(v) else { return false };
+                let text = text.to_lowercase();
+                match op {
+                    TextOp::Contains => text.contains(value.as_str()),
+                    TextOp::StartsWith => text.starts_with(value.as_str()),
+                    TextOp::EndsWith => text.ends_with(value.as_str()),
+                }
+            }),
+            Self::Regex { field, pattern, negate } => {
+                let found = lookup_all(doc, field).iter()
+                    .any(|v| as_text(v).is_some_and(|t| pattern.is_match(&t)));
+                found != *negate
+            }
+            Self::In { field, values } => lookup_all(doc, field).iter()
+                .any(|v| values.iter().any(|w| compare(v, w) == Some(Ordering::Equal))),
+            Self::Exists(field) => lookup_all(doc, field).iter().any(|v| !v.is_null()),
+        }
+    }
+}
+
+fn apply_stage(stage: &Stage, rows: Vec<Row>, intel: &dyn IntelLookup, hits: &mut Vec<IntelHit>) -> Vec<Row> {
+    match stage {
+        Stage::Where(expr) => rows.into_iter().filter(|r| expr.eval(&r.value)).collect(),
+        Stage::Stats { aggs, by } => aggregate(rows, aggs, by),
+        Stage::Sort(keys) => {
+            let mut rows = rows;
+            rows.sort_by(|a, b| {
+                for (field, descending) in keys {
+                    let ord = match (first(&a.value, field), first(&b.value, field)) {
+                        (Some(x), Some(y)) => compare(x, y).unwrap_or(Ordering::Equal),
+                        (Some(_), None) => Ordering::Greater,
+                        (None, Some(_)) => Ordering::Less,
+                        (None, None) => Ordering::Equal,
+                    };
+                    let ord = if *descending { ord.reverse() } else { ord };
+                    if ord != Ordering::Equal {
+                        return ord;
+                    }
+                }
+                Ordering::Equal
+            });
+            rows
+        }
+        Stage::Limit(n) => rows.into_iter().take(*n).collect(),
+        Stage::Fields(fields) => rows.into_iter()
+            .map(|r| Row { value: project(&r.value, fields), events: r.events })
+            .collect(),
+        Stage::Dedup(fields) => {
+            let mut seen = HashSet::new();
+            rows.into_iter()
+                .filter(|r| seen.insert(group_key(&r.value, fields).to_string()))
+                .collect()
+        }
+        Stage::Intel { field, alias } => rows.into_iter()
+            .filter_map(|mut row| {
+                let (value, record) = lookup_all(&row.value, field).iter()
+                    .filter_map(|v| as_text(v))
+                    .find_map(|v| intel.lookup(&v).map(|rec| (v, rec)))?;
+                if !hits.iter().any(|h| h.value == value) {
+                    hits.push(IntelHit {
+                        field: field.clone(),
+                        value: value.clone(),
+                        indicator_type: record.indicator_type,
+                        feeds: record.feeds.clone(),
+                        confidence: record.confidence,
+                    });
+                }
+                if let Value::Object(fields) = &mut row.value {
+                    let mut attached = serde_json::to_value(&record).unwrap_or(Value::Null);
+                    if let Value::Object(a) = &mut attached {
+                        a.insert("value".to_string(), Value::String(value));
+                    }
+                    fields.insert(alias.clone(), attached);
+                }
+                Some(row)
+            })
+            .collect(),
+    }
+}
+
+enum AggState {
+    Count(u64),
+    Distinct(HashSet<String>),
+    Sum(f64),
+    Avg(f64, u64),
+    Extreme(Option<Value>),
+    Values(Vec<Value>),
+}
+
+impl AggState {
+    fn new(func: AggFunc) -> Self {
+        match func {
+            AggFunc::Count => Self::Count(0),
+            AggFunc::DistinctCount => Self::Distinct(HashSet::new()),
+            AggFunc::Sum => Self::Sum(0.0),
+            AggFunc::Avg => Self::Avg(0.0, 0),
+            AggFunc::Min | AggFunc::Max => Self::Extreme(None),
+            AggFunc::Values => Self::Values(Vec::new()),
+        }
+    }
+
+    fn update(&mut self, agg: &Aggregation, row: &Value) {
+        let values = match &agg.field {
+            Some(field) => lookup_all(row, field).into_iter().filter(|v| !v.is_null()).collect(),
+            None => Vec::new(),
+        };
+        match self {
+            Self::Count(n) => {
+                if agg.field.is_none() || !values.is_empty() {
+                    *n += 1;
+                }
+            }
+            Self::Distinct(set) => set.extend(values.iter().map(|v| v.to_string())),
+            Self::Sum(total) => *total += values.iter().filter_map(|v| as_number(v)).sum::<f64>(),
+            Self::Avg(total, n) => {
+                for v in values.iter().filter_map(|v| as_number(v)) {
+                    *total += v;
+                    *n += 1;
+                }
+            }
+            Self::Extreme(current) => {
+                let wanted = if agg.func == AggFunc::Min { Ordering::Less } else { Ordering::Greater };
+                for v in values {
+                    let replace = match current.as_ref() {
+                        Some(c) => compare(v, c) == Some(wanted),
+                        None => true,
+                    };
+                    if replace {
+                        *current = Some(v.clone());
+                    }
+                }
+            }
+            Self::Values(items) => {
+                for v in values {
+                    if items.len() < MAX_AGG_VALUES && !items.contains(v) {
+                        items.push(v.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Self::Count(n) => Value::from(n),
+            Self::Distinct(set) => Value::from(set.len()),
+            Self::Sum(total) => number(total),
+            Self::Avg(_, 0) => Value::Null,
+            Self::Avg(total, n) => number(total / n as f64),
+            Self::Extreme(v) => v.unwrap_or(Value::Null),
+            Self::Values(items) => Value::Array(items),
+        }
+    }
+}
+
+fn aggregate(rows: Vec<Row>, aggs: &[Aggregation], by: &[String]) -> Vec<Row> {
+    struct Group {
+        key: Value,
+        states: Vec<AggState>,
+        events: Vec<String>,
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let key = group_key(&row.value, by);
+        let slot = *index.entry(key.to_string()).or_insert_with(|| {
+            groups.push(Group {
+                key: key.clone(),
+                states: aggs.iter().map(|a| AggState::new(a.func)).collect(),
+                events: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[slot];
+        for (state, agg) in group.states.iter_mut().zip(aggs) {
+            state.update(agg, &row.value);
+        }
+        let room = MAX_ROW_EVENTS.saturating_sub(group.events.len());
+        group.events.extend(row.events.into_iter().take(room));
+    }
+
+    groups.into_iter()
+        .map(|group| {
+            let mut fields = Map::new();
+            if let Value::Array(keys) = group.key {
+                for (field, value) in by.iter().zip(keys) {
+                    fields.insert(field.clone(), value);
+                }
+            }
+            for (state, agg) in group.states.into_iter().zip(aggs) {
+                fields.insert(agg.alias.clone(), state.finish());
+            }
+            Row { value: Value::Object(fields), events: group.events }
+        })
+        .collect()
+}
+
+/// All values at a dotted path, descending into arrays. A key that
+/// contains the whole path (e.g. `source.ip` after `stats ... by source.ip`)
+/// is used as is.
+fn lookup_all<'a>(doc: &'a Value, path: &str) -> Vec<&'a Value> {
+    fn walk<'a>(value: &'a Value, parts: &[&str], out: &mut Vec<&'a Value>) {
+        match (parts.split_first(), value) {
+            (None, Value::Array(items)) => out.extend(items.iter()),
+            (None, v) => out.push(v),
+            (Some((key, rest)), Value::Object(fields)) => {
+                if let Some(v) = fields.get(*key) {
+                    walk(v, rest, out);
+                }
+            }
+            (Some(_), Value::Array(items)) => {
+                for item in items {
+                    walk(item, parts, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(v) = doc.get(path) {
+        let mut out = Vec::new();
+        walk(v, &[], &mut out);
+        return out;
+    }
+    let parts: Vec<&str> = path.split('.').collect();
+    let mut out = Vec::new();
+    walk(doc, &parts, &mut out);
+    out
+}
+
+fn first<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    lookup_all(doc, path).into_iter().next()
+}
+
+fn group_key(doc: &Value, fields: &[String]) -> Value {
+    Value::Array(fields.iter()
+        .map(|f| first(doc, f).cloned().unwrap_or(Value::Null))
+        .collect())
+}
+
+fn project(doc: &Value, fields: &[String]) -> Value {
+    Value::Object(fields.iter()
+        .map(|f| (f.clone(), first(doc, f).cloned().unwrap_or(Value::Null)))
+        .collect())
+}
+
+/// Compare a document value with a query value; strings ignore case,
+/// severity names compare by rank and numeric strings compare as numbers
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::String(x), Value::String(y)) => {
+            if let (Some(x), Some(y)) = (severity_rank(x), severity_rank(y)) {
+                return Some(x.cmp(&y));
+            }
+            Some(x.to_lowercase().cmp(&y.to_lowercase()))
+        }
+        (Value::Number(_), _) | (_, Value::Number(_)) => as_number(a)?.partial_cmp(&as_number(b)?),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => (a == b).then_some(Ordering::Equal),
+    }
+}
+
+fn as_number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Num(f64),
+    Regex { pattern: String, flags: String },
+    Op(&'static str),
+    Pipe,
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(w) => write!(f, "'{}'", w),
+            Self::Str(s) => write!(f, "\"{}\"", s),
+            Self::Num(n) => write!(f, "{}", n),
+            Self::Regex { pattern, flags } => write!(f, "/{}/{}", pattern, flags),
+            Self::Op(op) => write!(f, "'{}'", op),
+            Self::Pipe => write!(f, "'|'"),
+            Self::LParen => write!(f, "'('"),
+            Self::RParen => write!(f, "')'"),
+            Self::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn syntax(message: String) -> HuntError {
+    HuntError::InvalidQuery(message)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | ':' | '@' | '*')
+}
+
+fn lex(text: &str) -> Result<Vec<Token>, HuntError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        i += 1;
+        match c {
+            c if c.is_whitespace() => {}
+            '|' => tokens.push(Token::Pipe),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '=' | '!' | '<' | '>' => {
+                let (op, width) = match (c, next) {
+                    ('=', Some('~')) => ("=~", 2),
+                    ('=', Some('=')) => ("=", 2),
+                    ('!', Some('~')) => ("!~", 2),
+                    ('!', Some('=')) => ("!=", 2),
+                    ('<', Some('=')) => ("<=", 2),
+                    ('>', Some('=')) => (">=", 2),
+                    ('=', _) => ("=", 1),
+                    ('<', _) => ("<", 1),
+                    ('>', _) => (">", 1),
+                    _ => return Err(syntax("'!' must be followed by '=' or '~'".to_string())),
+                };
+                i += width - 1;
+                tokens.push(Token::Op(op));
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    let Some(&ch) = chars.get(i) else {
+                        return Err(syntax("unterminated string".to_string()));
+                    };
+                    i += 1;
+                    match ch {
+                        '\\' => {
+                            let escaped = chars.get(i).copied()
+                                .ok_or_else(|| syntax("unterminated string".to_string()))?;
+                            i += 1;
+                            value.push(match escaped {
+                                'n' => '\n',
+                                't' => '\t',
+                                other => other,
+                            });
+                        }
+                        ch if ch == c => break,
+                        ch => value.push(ch),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '/' => {
+                let mut pattern = String::new();
+                loop {
+                    let Some(&ch) = chars.get(i) else {
+                        return Err(syntax("unterminated regex".to_string()));
+                    };
+                    i += 1;
+                    match ch {
+                        '\\' if chars.get(i) == Some(&'/') => {
+                            pattern.push('/');
+                            i += 1;
+                        }
+                        '\\' => {
+                            pattern.push('\\');
+                            if let Some(&escaped) = chars.get(i) {
+                                pattern.push(escaped);
+                                i += 1;
+                            }
+                        }
+                        '/' => break,
+                        ch => pattern.push(ch),
+                    }
+                }
+                let mut flags = String::new();
+                while let Some(&ch) = chars.get(i).filter(|c| c.is_ascii_alphabetic()) {
+                    flags.push(ch);
+                    i += 1;
+                }
+                tokens.push(Token::Regex { pattern, flags });
+            }
+            c if is_word_char(c) => {
+                let start = i - 1;
+                while chars.get(i).is_some_and(|c| is_word_char(*c)) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match word.parse::<f64>() {
+                    Ok(n) if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                        tokens.push(Token::Num(n))
+                    }
+                    _ => tokens.push(Token::Word(word)),
+                }
+            }
+            other => return Err(syntax(format!("unexpected character '{}'", other))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consume a keyword if it comes next
+    fn keyword(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(word) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), HuntError> {
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(syntax(format!("expected {}, found {}", expected, token))),
+            None => Err(syntax(format!("expected {}, found end of query", expected))),
+        }
+    }
+
+    fn field(&mut self) -> Result<String, HuntError> {
+        match self.advance() {
+            Some(Token::Word(w)) => Ok(w),
+            Some(token) => Err(syntax(format!("expected a field name, found {}", token))),
+            None => Err(syntax("expected a field name, found end of query".to_string())),
+        }
+    }
+
+    fn field_list(&mut self) -> Result<Vec<String>, HuntError> {
+        let mut fields = vec![self.field()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            fields.push(self.field()?);
+        }
+        Ok(fields)
+    }
+
+    fn literal(&mut self) -> Result<Value, HuntError> {
+        match self.advance() {
+            Some(Token::Str(s)) => Ok(Value::String(s)),
+            Some(Token::Num(n)) => Ok(number(n)),
+            Some(Token::Word(w)) => Ok(match w.to_ascii_lowercase().as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => Value::String(w),
+            }),
+            Some(token) => Err(syntax(format!("expected a value, found {}", token))),
+            None => Err(syntax("expected a value, found end of query".to_string())),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, HuntError> {
+        let mut left = self.conjunction()?;
+        while self.keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.conjunction()?));
+        }
+        Ok(left)
+    }
+
+    fn conjunction(&mut self) -> Result<Expr, HuntError> {
+        let mut left = self.unary()?;
+        while self.keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, HuntError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let inner = self.expr()?;
+            self.expect(Token::RParen)?;
+            return Ok(inner);
+        }
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<Expr, HuntError> {
+        let field = self.field()?;
+        match self.advance() {
+            Some(Token::Op(op @ ("=~" | "!~"))) => {
+                let (pattern, flags) = match self.advance() {
+                    Some(Token::Regex { pattern, flags }) => (pattern, flags),
+                    Some(Token::Str(pattern)) => (pattern, String::new()),
+                    other => return Err(syntax(format!(
+                        "expected a regex after '{}', found {}",
+                        op,
+                        other.map(|t| t.to_string()).unwrap_or_else(|| "end of query".to_string()),
+                    ))),
+                };
+                Ok(Expr::Regex { field, pattern: compile_regex(&pattern, &flags)?, negate: op == "!~" })
+            }
+            Some(Token::Op(op)) => {
+                let op = match op {
+                    "=" => CmpOp::Eq,
+                    "!=" => CmpOp::Ne,
+                    ">" => CmpOp::Gt,
+                    ">=" => CmpOp::Gte,
+                    "<" => CmpOp::Lt,
+                    _ => CmpOp::Lte,
+                };
+                Ok(Expr::Compare { field, op, value: self.literal()? })
+            }
+            Some(Token::Word(w)) => match w.to_ascii_lowercase().as_str() {
+                "contains" | "startswith" | "endswith" => {
+                    let op = match w.to_ascii_lowercase().as_str() {
+                        "contains" => TextOp::Contains,
+                        "startswith" => TextOp::StartsWith,
+                        _ => TextOp::EndsWith,
+                    };
+                    let value = as_text(&self.literal()?)
+                        .ok_or_else(|| syntax(format!("'{}' needs a text value", w)))?;
+                    Ok(Expr::Text { field, op, value: value.to_lowercase() })
+                }
+                "in" => {
+                    self.expect(Token::LParen)?;
+                    let mut values = vec![self.literal()?];
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        values.push(self.literal()?);
+                    }
+                    self.expect(Token::RParen)?;
+                    Ok(Expr::In { field, values })
+                }
+                "exists" => Ok(Expr::Exists(field)),
+                _ => Err(syntax(format!("unknown operator '{}' after {}", w, field))),
+            },
+            Some(token) => Err(syntax(format!("expected an operator after {}, found {}", field, token))),
+            None => Err(syntax(format!("expected an operator after {}", field))),
+        }
+    }
+
+    fn stage(&mut self) -> Result<Stage, HuntError> {
+        let name = self.field()?.to_ascii_lowercase();
+        let stage = match name.as_str() {
+            "where" => Stage::Where(self.expr()?),
+            "stats" => {
+                let mut aggs = vec![self.aggregation()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    aggs.push(self.aggregation()?);
+                }
+                let by = if self.keyword("by") { self.field_list()? } else { Vec::new() };
+                Stage::Stats { aggs, by }
+            }
+            "sort" => {
+                let mut keys = Vec::new();
+                loop {
+                    let field = self.field()?;
+                    let descending = self.keyword("desc");
+                    if !descending {
+                        self.keyword("asc");
+                    }
+                    keys.push((field, descending));
+                    if self.peek() != Some(&Token::Comma) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                Stage::Sort(keys)
+            }
+            "limit" | "head" => match self.advance() {
+                Some(Token::Num(n)) if n >= 0.0 && n.fract() == 0.0 => Stage::Limit(n as usize),
+                _ => return Err(syntax(format!("'{}' needs a whole number", name))),
+            },
+            "fields" => Stage::Fields(self.field_list()?),
+            "dedup" => Stage::Dedup(self.field_list()?),
+            "intel" => {
+                let field = self.field()?;
+                let alias = if self.keyword("as") { self.field()? } else { "intel".to_string() };
+                Stage::Intel { field, alias }
+            }
+            other => return Err(syntax(format!("unknown stage '{}'", other))),
+        };
+
+        match self.peek() {
+            None | Some(Token::Pipe) => Ok(stage),
+            Some(token) => Err(syntax(format!("unexpected {} after '{}' stage", token, name))),
+        }
+    }
+
+    fn aggregation(&mut self) -> Result<Aggregation, HuntError> {
+        let name = self.field()?.to_ascii_lowercase();
+        let func = match name.as_str() {
+            "count" => AggFunc::Count,
+            "dc" | "distinct_count" => AggFunc::DistinctCount,
+            "sum" => AggFunc::Sum,
+            "avg" => AggFunc::Avg,
+            "min" => AggFunc::Min,
+            "max" => AggFunc::Max,
+            "values" => AggFunc::Values,
+            other => return Err(syntax(format!("unknown aggregation '{}'", other))),
+        };
+        self.expect(Token::LParen)?;
+        let field = match self.peek() {
+            Some(Token::RParen) => None,
+            _ => Some(self.field()?),
+        };
+        self.expect(Token::RParen)?;
+        if field.is_none() && func != AggFunc::Count {
+            return Err(syntax(format!("'{}' needs a field", name)));
+        }
+
+        let alias = if self.keyword("as") {
+            self.field()?
+        } else {
+            match &field {
+                Some(f) => format!("{}_{}", name, f.replace('.', "_")),
+                None => name.clone(),
+            }
+        };
+        Ok(Aggregation { func, field, alias })
+    }
+}
+
+fn compile_regex(pattern: &str, flags: &str) -> Result<regex::Regex, HuntError> {
+    let mut builder = regex::RegexBuilder::new(pattern);
+    builder.size_limit(REGEX_SIZE_LIMIT);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            other => return Err(syntax(format!("unknown regex flag '{}'", other))),
+        };
+    }
+    builder.build().map_err(|e| syntax(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(query: &str, docs: &[Value]) -> HuntResult {
+        let ids: Vec<String> = (0..docs.len()).map(|i| format!("e{}", i)).collect();
+        HuntQuery::parse(query).unwrap()
+            .execute(ids.iter().map(String::as_str).zip(docs.iter()), &NoIntel)
+    }
+
+    fn error(query: &str) -> String {
+        match HuntQuery::parse(query) {
+            Err(HuntError::InvalidQuery(message)) => message,
+            other => panic!("expected a syntax error for {:?}, got {:?}", query, other.map(|q| q.text)),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(error("user = \"bob"), "unterminated string");
+        assert_eq!(error("user ! bob"), "'!' must be followed by '=' or '~'");
+        assert_eq!(error("user bob"), "unknown operator 'bob' after user");
+        assert_eq!(error("| stats sum() by user"), "'sum' needs a field");
+        assert_eq!(error("| limit 1.5"), "'limit' needs a whole number");
+        assert_eq!(error("| sort user | explode user"), "unknown stage 'explode'");
+        assert_eq!(error("| limit 5 10"), "unexpected 10 after 'limit' stage");
+        assert_eq!(error("user =~ /bob/q"), "unknown regex flag 'q'");
+        assert!(error("(user = bob").starts_with("expected ')'"));
+        assert!(HuntQuery::parse("").unwrap().filter.is_none());
+    }
+
+    #[test]
+    fn test_filter_semantics() {
+        let doc = json!({
+            "event_type": "BruteForceAttempt",
+            "severity": "High",
+            "source": { "ip": "203.0.113.7" },
+            "indicators": [
+                { "indicator_type": "Username", "value": "Alice@corp.example" },
+                { "indicator_type": "IpAddress", "value": "203.0.113.7" }
+            ],
+            "raw_data": { "bytes": "1500", "country": "NL" }
+        });
+        let matches = |query: &str| HuntQuery::parse(query).unwrap().matches(&doc);
+
+        assert!(matches("event_type = bruteforceattempt"));
+        assert!(matches("severity >= medium and severity < critical"));
+        assert!(!matches("severity > high"));
+        assert!(matches("indicators.value = \"alice@corp.example\""));
+        assert!(matches("indicators.value endswith \"@CORP.EXAMPLE\""));
+        assert!(matches("indicators.value =~ /^alice@/i"));
+        assert!(!matches("indicators.value =~ \"^alice@\""));
+        assert!(matches("source.ip !~ /^10\\./"));
+        assert!(matches("raw_data.bytes > 1000 and raw_data.bytes <= 1500"));
+        assert!(matches("raw_data.country in (US, nl, DE)"));
+        assert!(matches("raw_data.user exists or source.ip exists"));
+        assert!(!matches("not source.ip exists"));
+        // `and` binds tighter than `or`
+        assert!(matches("severity = low and raw_data.country = US or event_type = BruteForceAttempt"));
+        assert!(!matches("severity = low and (raw_data.country = US or event_type = BruteForceAttempt)"));
+        assert!(matches("raw_data.country != US"));
+        assert!(!matches("indicators.indicator_type != Username"));
+    }
+
+    #[test]
+    fn test_stats_sort_limit() {
+        let docs = vec![
+            json!({ "ip": "10.0.0.1", "user": "alice", "bytes": 100 }),
+            json!({ "ip": "10.0.0.2", "user": "bob", "bytes": 300 }),
+            json!({ "ip": "10.0.0.1", "user": "carol", "bytes": 200 }),
+            json!({ "ip": "10.0.0.1", "user": "alice" }),
+            json!({ "ip": "10.0.0.3", "user": "dave", "bytes": 50 }),
+        ];
+
+        let result = run(
+            "| stats count() as n, dc(user) as users, avg(bytes), max(bytes) as peak, values(user) by ip | sort n desc | limit 1",
+            &docs,
+        );
+        assert_eq!(result.rows, vec![json!({
+            "ip": "10.0.0.1",
+            "n": 3,
+            "users": 2,
+            "avg_bytes": 150,
+            "peak": 200,
+            "values_user": ["alice", "carol"],
+        })]);
+        assert_eq!(result.event_ids, vec!["e0", "e2", "e3"]);
+        assert_eq!((result.scanned, result.matched), (5, 5));
+
+        let result = run("bytes exists | sort bytes | dedup ip | fields ip, bytes", &docs);
+        assert_eq!(result.rows, vec![
+            json!({ "ip": "10.0.0.3", "bytes": 50 }),
+            json!({ "ip": "10.0.0.1", "bytes": 100 }),
+            json!({ "ip": "10.0.0.2", "bytes": 300 }),
+        ]);
+        assert_eq!(result.matched, 4);
+    }
+
+    #[test]
+    fn test_intel_stage_attaches_matches() {
+        struct Known;
+        impl IntelLookup for Known {
+            fn lookup(&self, value: &str) -> Option<IntelRecord> {
+                (value == "198.51.100.9").then(|| IntelRecord {
+                    indicator_type: IndicatorType::IpAddress,
+                    feeds: vec!["abuse.ch".to_string()],
+                    confidence: 0.9,
+                })
+            }
+        }
+
+        let docs = [
+            json!({ "dst": ["192.0.2.1", "198.51.100.9"] }),
+            json!({ "dst": ["192.0.2.2"] }),
+            json!({ "dst": "198.51.100.9" }),
+        ];
+        let ids = ["a", "b", "c"];
+        let result = HuntQuery::parse("| intel dst as ti").unwrap()
+            .execute(ids.into_iter().zip(docs.iter()), &Known);
+
+        assert_eq!(result.event_ids, vec!["a", "c"]);
+        assert_eq!(result.rows[0]["ti"]["value"], "198.51.100.9");
+        assert_eq!(result.rows[0]["ti"]["feeds"], json!(["abuse.ch"]));
+        assert_eq!(result.intel_hits.len(), 1);
+    }
+}
@@ -0,0 +1,452 @@
+//! Saved hunts
+//!
+//! A saved hunt is a named query over a lookback window of the event store,
+//! run on demand or on a schedule. Scheduled runs that return rows raise a
+//! [`SecurityAlert`], unless every event behind them was already part of
+//! the hunt's previous alert.
+
+use super::query::{HuntQuery, HuntResult};
+use super::{HuntError, HuntPrincipal, RunStatus, RunTrigger, ThreatHunter};
+use crate::siem::TimeRange;
+use crate::{AlertEnrichment, AlertStatus, SecurityAlert, Severity, ThreatIntelMatch};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+
+/// Events listed on a hunt alert
+const MAX_ALERT_EVENTS: usize = 100;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SavedHunt {
+    pub id: String,
+    pub tenant_id: String,
+    pub name: String,
+    pub description: String,
+    pub owner: String,
+    pub query: String,
+    /// Window searched by each run
+    pub lookback_minutes: i64,
+    pub schedule: Option<HuntSchedule>,
+    pub alert: HuntAlertConfig,
+    pub mitre_attack: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HuntSchedule {
+    pub interval_secs: u64,
+    pub enabled: bool,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+/// What a scheduled hit raises
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HuntAlertConfig {
+    pub enabled: bool,
+    pub severity: Severity,
+    /// Rows needed before alerting
+    pub min_rows: usize,
+    /// Defaults to `Hunt: <name>`
+    pub alert_type: Option<String>,
+}
+
+impl Default for HuntAlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            severity: Severity::Medium,
+            min_rows: 1,
+            alert_type: None,
+        }
+    }
+}
+
+/// One execution of a saved hunt
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HuntRun {
+    pub id: String,
+    pub hunt_id: String,
+    pub tenant_id: String,
+    pub trigger: RunTrigger,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: RunStatus,
+    pub result: Option<HuntResult>,
+    pub error: Option<String>,
+    /// Alert raised by this run
+    pub alert: Option<SecurityAlert>,
+}
+
+impl SavedHunt {
+    pub fn new(name: &str, query: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            tenant_id: String::new(),
+            name: name.to_string(),
+            description: String::new(),
+            owner: String::new(),
+            query: query.to_string(),
+            lookback_minutes: 60,
+            schedule: None,
+            alert: HuntAlertConfig::default(),
+            mitre_attack: vec![],
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Run every `interval_secs`
+    pub fn every(mut self, interval_secs: u64) -> Self {
+        self.schedule = Some(HuntSchedule { interval_secs, enabled: true, next_run: None });
+        self
+    }
+
+    pub fn with_lookback(mut self, minutes: i64) -> Self {
+        self.lookback_minutes = minutes;
+        self
+    }
+
+    pub fn with_alert(mut self, alert: HuntAlertConfig) -> Self {
+        self.alert = alert;
+        self
+    }
+
+    pub fn validate(&self) -> Result<HuntQuery, HuntError> {
+        if self.name.trim().is_empty() {
+            return Err(HuntError::InvalidQuery("hunt needs a name".to_string()));
+        }
+        if self.lookback_minutes < 1 {
+            return Err(HuntError::InvalidQuery(format!(
+                "lookback of {} minutes is too short", self.lookback_minutes)));
+        }
+        HuntQuery::parse(&self.query)
+    }
+}
+
+impl ThreatHunter {
+    /// Save a new hunt owned by `principal`
+    pub fn save_hunt(&self, principal: &HuntPrincipal, mut hunt: SavedHunt) -> Result<String, HuntError> {
+        hunt.validate()?;
+        hunt.tenant_id = principal.tenant_id.clone();
+        hunt.owner = principal.user_id.clone();
+        if hunt.id.is_empty() || self.hunts.contains_key(&hunt.id) {
+            hunt.id = uuid::Uuid::new_v4().to_string();
+        }
+        hunt.created_at = Utc::now();
+        hunt.updated_at = hunt.created_at;
+        if let Some(schedule) = hunt.schedule.as_mut() {
+            arm(schedule);
+        }
+
+        let id = hunt.id.clone();
+        tracing::info!("Saved hunt {} created by {}", id, principal.user_id);
+        self.hunts.insert(id.clone(), hunt);
+        Ok(id)
+    }
+
+    /// Replace a hunt's definition; only its owner may
+    pub fn update_hunt(&self, principal: &HuntPrincipal, hunt: SavedHunt) -> Result<(), HuntError> {
+        let current = self.authorize_hunt(principal, &hunt.id, true)?;
+        hunt.validate()?;
+
+        let mut updated = SavedHunt {
+            tenant_id: current.tenant_id,
+            owner: current.owner,
+            created_at: current.created_at,
+            updated_at: Utc::now(),
+            ..hunt
+        };
+        if let Some(schedule) = updated.schedule.as_mut() {
+            arm(schedule);
+        }
+        self.hunts.insert(updated.id.clone(), updated);
+        Ok(())
+    }
+
+    pub fn get_hunt(&self, principal: &HuntPrincipal, id: &str) -> Result<SavedHunt, HuntError> {
+        self.authorize_hunt(principal, id, false)
+    }
+
+    /// Hunts of the principal's tenant
+    pub fn list_hunts(&self, principal: &HuntPrincipal) -> Vec<SavedHunt> {
+        self.hunts.iter()
+            .filter(|h| h.tenant_id == principal.tenant_id)
+            .map(|h| h.clone())
+            .collect()
+    }
+
+    pub fn delete_hunt(&self, principal: &HuntPrincipal, id: &str) -> Result<(), HuntError> {
+        self.authorize_hunt(principal, id, true)?;
+        self.hunts.remove(id);
+        self.hunt_runs.remove(id);
+        Ok(())
+    }
+
+    /// Stored runs of a hunt, oldest first
+    pub fn hunt_runs(&self, principal: &HuntPrincipal, id: &str) -> Result<Vec<HuntRun>, HuntError> {
+        self.authorize_hunt(principal, id, false)?;
+        Ok(self.hunt_runs.get(id).map(|r| r.iter().cloned().collect()).unwrap_or_default())
+    }
+
+    /// Run a saved hunt now. Manual runs never raise alerts.
+    pub fn run_hunt(&self, principal: &HuntPrincipal, id: &str) -> Result<HuntRun, HuntError> {
+        let hunt = self.authorize_hunt(principal, id, false)?;
+        let run = self.execute_hunt(&hunt, RunTrigger::Manual { user_id: principal.user_id.clone() });
+        self.store_hunt_run(run.clone());
+        Ok(run)
+    }
+
+    /// Execute every scheduled hunt whose next run is due. Alerts raised
+    /// are returned on the runs for the caller to route.
+    pub fn run_due_hunts(&self) -> Vec<HuntRun> {
+        let now = Utc::now();
+        let mut due = Vec::new();
+
+        for mut entry in self.hunts.iter_mut() {
+            let Some(schedule) = entry.schedule.as_mut() else { continue };
+            if !schedule.enabled || schedule.next_run.is_some_and(|t| t > now) {
+                continue;
+            }
+            schedule.next_run = Some(now + interval(schedule));
+            due.push(entry.clone());
+        }
+
+        due.iter()
+            .map(|hunt| {
+                let run = self.execute_hunt(hunt, RunTrigger::Schedule);
+                self.store_hunt_run(run.clone());
+                run
+            })
+            .collect()
+    }
+
+    fn execute_hunt(&self, hunt: &SavedHunt, trigger: RunTrigger) -> HuntRun {
+        let started_at = Utc::now();
+        let mut run = HuntRun {
+            id: uuid::Uuid::new_v4().to_string(),
+            hunt_id: hunt.id.clone(),
+            tenant_id: hunt.tenant_id.clone(),
+            trigger,
+            started_at,
+            finished_at: started_at,
+            status: RunStatus::Succeeded,
+            result: None,
+            error: None,
+            alert: None,
+        };
+
+        let query = match HuntQuery::parse(&hunt.query) {
+            Ok(query) => query,
+            Err(e) => {
+                tracing::warn!("Saved hunt {} has an invalid query: {}", hunt.id, e);
+                run.status = RunStatus::Failed;
+                run.error = Some(e.to_string());
+                run.finished_at = Utc::now();
+                return run;
+            }
+        };
+        let range = TimeRange {
+            start: started_at - Duration::minutes(hunt.lookback_minutes.max(1)),
+            end: started_at,
+        };
+        let result = self.events.search(&query, &range, Some(&hunt.tenant_id), self);
+
+        if matches!(run.trigger, RunTrigger::Schedule)
+            && hunt.alert.enabled
+            && !result.is_empty()
+            && result.rows.len() >= hunt.alert.min_rows
+            && self.has_new_events(&hunt.id, &result)
+        {
+            let alert = hunt_alert(hunt, &result);
+            tracing::info!("Saved hunt {} raised alert {} with {} rows", hunt.id, alert.id, result.rows.len());
+            run.alert = Some(alert);
+        }
+
+        run.result = Some(result);
+        run.finished_at = Utc::now();
+        run
+    }
+
+    /// Whether a result has events not covered by the hunt's last alert
+    fn has_new_events(&self, hunt_id: &str, result: &HuntResult) -> bool {
+        let Some(runs) = self.hunt_runs.get(hunt_id) else { return true };
+        let Some(last) = runs.iter().rev().find_map(|r| r.alert.as_ref()) else { return true };
+        let alerted: HashSet<&str> = last.events.iter().map(|e| e.as_str()).collect();
+        result.event_ids.iter().take(MAX_ALERT_EVENTS).any(|e| !alerted.contains(e.as_str()))
+    }
+
+    fn authorize_hunt(&self, principal: &HuntPrincipal, id: &str, owner: bool) -> Result<SavedHunt, HuntError> {
+        let hunt = self.hunts.get(id)
+            .filter(|h| h.tenant_id == principal.tenant_id)
+            .map(|h| h.clone())
+            .ok_or_else(|| HuntError::NotFound(id.to_string()))?;
+        if owner && hunt.owner != principal.user_id {
+            return Err(HuntError::Forbidden(format!("{} does not own hunt {}", principal.user_id, id)));
+        }
+        Ok(hunt)
+    }
+
+    fn store_hunt_run(&self, run: HuntRun) {
+        let mut history = self.hunt_runs.entry(run.hunt_id.clone()).or_default();
+        history.push_back(run);
+        while history.len() > self.max_snapshots {
+            history.pop_front();
+        }
+    }
+}
+
+fn arm(schedule: &mut HuntSchedule) {
+    if schedule.next_run.is_none() {
+        schedule.next_run = Some(Utc::now() + interval(schedule));
+    }
+}
+
+fn interval(schedule: &HuntSchedule) -> Duration {
+    Duration::seconds(schedule.interval_secs.max(60) as i64)
+}
+
+fn hunt_alert(hunt: &SavedHunt, result: &HuntResult) -> SecurityAlert {
+    let now = Utc::now();
+    let threat_intel: Vec<ThreatIntelMatch> = result.intel_hits.iter()
+        .map(|hit| ThreatIntelMatch {
+            feed: hit.feeds.first().cloned().unwrap_or_default(),
+            indicator: hit.value.clone(),
+            threat_type: "malicious".to_string(),
+            confidence: hit.confidence,
+        })
+        .collect();
+    let base = match hunt.alert.severity {
+        Severity::Info => 10.0,
+        Severity::Low => 25.0,
+        Severity::Medium => 50.0,
+        Severity::High => 75.0,
+        Severity::Critical => 95.0,
+    };
+    let risk_score = (base + threat_intel.len() as f64 * 10.0).min(100.0);
+
+    SecurityAlert {
+        id: uuid::Uuid::new_v4().to_string(),
+        events: result.event_ids.iter().take(MAX_ALERT_EVENTS).cloned().collect(),
+        alert_type: hunt.alert.alert_type.clone().unwrap_or_else(|| format!("Hunt: {}", hunt.name)),
+        severity: hunt.alert.severity,
+        status: AlertStatus::New,
+        created_at: now,
+        updated_at: now,
+        assigned_to: None,
+        mitre_tactics: vec![],
+        mitre_techniques: hunt.mitre_attack.clone(),
+        enrichment: AlertEnrichment {
+            threat_intel,
+            risk_score,
+            ..AlertEnrichment::default()
+        },
+        case_id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventSource, EventType, SecurityEvent};
+
+    fn principal(user: &str, tenant: &str) -> HuntPrincipal {
+        HuntPrincipal { user_id: user.to_string(), tenant_id: tenant.to_string() }
+    }
+
+    fn record(hunter: &ThreatHunter, id: &str, tenant: &str) {
+        hunter.record_event(&SecurityEvent {
+            id: id.to_string(),
+            event_type: EventType::AuthenticationFailure,
+            severity: Severity::Medium,
+            source: EventSource {
+                system: "idp".to_string(),
+                component: "sso".to_string(),
+                host: None,
+                ip: Some("203.0.113.5".to_string()),
+            },
+            timestamp: Utc::now(),
+            description: "login failed".to_string(),
+            raw_data: serde_json::Value::Null,
+            indicators: vec![],
+            tags: vec![],
+            tenant_id: Some(tenant.to_string()),
+        }, "default");
+    }
+
+    /// Save a scheduled hunt that is already due
+    fn save_due(hunter: &ThreatHunter, analyst: &HuntPrincipal, query: &str) -> String {
+        let mut hunt = SavedHunt::new("Failed logins", query).every(300).with_alert(HuntAlertConfig {
+            severity: Severity::High,
+            alert_type: Some("HuntHit".to_string()),
+            ..Default::default()
+        });
+        hunt.schedule.as_mut().unwrap().next_run = Some(Utc::now() - Duration::seconds(1));
+        hunter.save_hunt(analyst, hunt).unwrap()
+    }
+
+    #[test]
+    fn test_scheduled_hunt_alerts_only_on_new_events() {
+        let hunter = ThreatHunter::new();
+        let analyst = principal("alice", "acme");
+        record(&hunter, "e1", "acme");
+        record(&hunter, "x1", "globex");
+        let id = save_due(&hunter, &analyst, "event_type = AuthenticationFailure");
+
+        let runs = hunter.run_due_hunts();
+        assert_eq!(runs.len(), 1);
+        let alert = runs[0].alert.as_ref().expect("first hit raises an alert");
+        assert_eq!((alert.alert_type.as_str(), alert.severity), ("HuntHit", Severity::High));
+        assert_eq!(alert.events, vec!["e1"]);
+        // Not due again until the interval passes
+        assert!(hunter.run_due_hunts().is_empty());
+
+        let rearm = |hunter: &ThreatHunter| {
+            hunter.hunts.get_mut(&id).unwrap().schedule.as_mut().unwrap().next_run = Some(Utc::now());
+        };
+        rearm(&hunter);
+        let runs = hunter.run_due_hunts();
+        assert!(runs[0].alert.is_none());
+        assert_eq!(runs[0].result.as_ref().unwrap().rows.len(), 1);
+
+        record(&hunter, "e2", "acme");
+        rearm(&hunter);
+        assert_eq!(hunter.run_due_hunts()[0].alert.as_ref().unwrap().events, vec!["e1", "e2"]);
+
+        // Manual runs report rows but never alert
+        let manual = hunter.run_hunt(&analyst, &id).unwrap();
+        assert!(manual.alert.is_none());
+        assert_eq!(hunter.hunt_runs(&analyst, &id).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_hunt_ownership_and_validation() {
+        let hunter = ThreatHunter::new();
+        let alice = principal("alice", "acme");
+        let bob = principal("bob", "acme");
+        let mallory = principal("mallory", "globex");
+
+        assert!(hunter.save_hunt(&alice, SavedHunt::new("", "user exists")).is_err());
+        assert!(hunter.save_hunt(&alice, SavedHunt::new("Bad", "user =")).is_err());
+        assert!(hunter.save_hunt(&alice, SavedHunt::new("Short", "user exists").with_lookback(0)).is_err());
+
+        let id = hunter.save_hunt(&alice, SavedHunt::new("Logins", "user exists").every(10)).unwrap();
+        let hunt = hunter.get_hunt(&bob, &id).unwrap();
+        assert_eq!(hunt.owner, "alice");
+        assert!(hunt.schedule.unwrap().next_run.unwrap() >= Utc::now() + Duration::seconds(59));
+
+        assert!(matches!(hunter.get_hunt(&mallory, &id), Err(HuntError::NotFound(_))));
+        assert!(hunter.list_hunts(&mallory).is_empty());
+        assert!(matches!(hunter.delete_hunt(&bob, &id), Err(HuntError::Forbidden(_))));
+
+        let mut renamed = hunter.get_hunt(&alice, &id).unwrap();
+        renamed.name = "Renamed".to_string();
+        renamed.owner = "bob".to_string();
+        hunter.update_hunt(&alice, renamed).unwrap();
+        let hunt = hunter.get_hunt(&alice, &id).unwrap();
+        assert_eq!((hunt.name.as_str(), hunt.owner.as_str()), ("Renamed", "alice"));
+
+        hunter.delete_hunt(&alice, &id).unwrap();
+        assert!(hunter.list_hunts(&alice).is_empty());
+    }
+}
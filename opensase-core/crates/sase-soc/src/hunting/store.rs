@@ -0,0 +1,208 @@
+//! Hunting event store
+//!
+//! Every ingested [`SecurityEvent`] is kept here, in timestamp order, as the
+//! JSON document hunt queries run against. Retention is bounded by both
+//! event count and age; the oldest events go first.
+
+use super::query::{HuntQuery, HuntResult, IntelLookup};
+use crate::siem::TimeRange;
+use crate::{SecurityEvent, Severity};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Stored form of one event
+#[derive(Debug, Clone)]
+pub struct StoredEvent {
+    pub id: String,
+    pub tenant_id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Serialized event plus derived fields such as `severity_level`
+    pub doc: Value,
+}
+
+pub struct EventStore {
+    events: parking_lot::RwLock<VecDeque<StoredEvent>>,
+    max_events: usize,
+    retention: Duration,
+}
+
+impl EventStore {
+    pub fn new() -> Self {
+        Self {
+            events: parking_lot::RwLock::new(VecDeque::new()),
+            max_events: 1_000_000,
+            retention: Duration::days(30),
+        }
+    }
+
+    pub fn with_max_events(mut self, max: usize) -> Self {
+        self.max_events = max.max(1);
+        self
+    }
+
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Store an event under its own tenant, or `default_tenant` if it has
+    /// none. Late arrivals are placed by timestamp.
+    pub fn append(&self, event: &SecurityEvent, default_tenant: &str) {
+        let mut doc = match serde_json::to_value(event) {
+            Ok(doc) => doc,
+            Err(e) => {
+                tracing::warn!("Event {} not stored for hunting: {}", event.id, e);
+                return;
+            }
+        };
+        if let Value::Object(fields) = &mut doc {
+            fields.insert("severity_level".to_string(), Value::from(event.severity as u8));
+        }
+        let stored = StoredEvent {
+            id: event.id.clone(),
+            tenant_id: event.tenant_id.clone().unwrap_or_else(|| default_tenant.to_string()),
+            timestamp: event.timestamp,
+            doc,
+        };
+
+        let mut events = self.events.write();
+        if events.back().is_none_or(|last| last.timestamp <= stored.timestamp) {
+            events.push_back(stored);
+        } else {
+            let at = events.partition_point(|e| e.timestamp <= stored.timestamp);
+            events.insert(at, stored);
+        }
+
+        while events.len() > self.max_events {
+            events.pop_front();
+        }
+        let cutoff = Utc::now() - self.retention;
+        while events.front().is_some_and(|e| e.timestamp < cutoff) {
+            events.pop_front();
+        }
+    }
+
+    /// Run a query over the events in `range`. With a tenant, only that
+    /// tenant's events are visible.
+    pub fn search(
+        &self,
+        query: &HuntQuery,
+        range: &TimeRange,
+        tenant_id: Option<&str>,
+        intel: &dyn IntelLookup,
+    ) -> HuntResult {
+        let events = self.events.read();
+        let start = events.partition_point(|e| e.timestamp < range.start);
+        let candidates = events.range(start..)
+            .take_while(|e| e.timestamp <= range.end)
+            .filter(|e| tenant_id.is_none() || tenant_id == Some(e.tenant_id.as_str()))
+            .map(|e| (e.id.as_str(), &e.doc));
+        query.execute(candidates, intel)
+    }
+
+    /// Stored events by id, e.g. to pull up the events behind a hunt hit
+    pub fn get_events(&self, ids: &[String]) -> Vec<SecurityEvent> {
+        self.events.read()
+            .iter()
+            .filter(|e| ids.contains(&e.id))
+            .filter_map(|e| serde_json::from_value(e.doc.clone()).ok())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.read().is_empty()
+    }
+
+    /// Timestamp of the oldest event still held
+    pub fn oldest(&self) -> Option<DateTime<Utc>> {
+        self.events.read().front().map(|e| e.timestamp)
+    }
+}
+
+impl Default for EventStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rank used when comparing severity names in queries
+pub(crate) fn severity_rank(name: &str) -> Option<u8> {
+    let severity = match name.to_ascii_lowercase().as_str() {
+        "info" => Severity::Info,
+        "low" => Severity::Low,
+        "medium" => Severity::Medium,
+        "high" => Severity::High,
+        "critical" => Severity::Critical,
+        _ => return None,
+    };
+    Some(severity as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventSource, EventType};
+
+    fn event(id: &str, tenant: Option<&str>, age_mins: i64) -> SecurityEvent {
+        SecurityEvent {
+            id: id.to_string(),
+            event_type: EventType::PortScan,
+            severity: Severity::High,
+            source: EventSource {
+                system: "ngfw".to_string(),
+                component: "ids".to_string(),
+                host: None,
+                ip: Some("192.0.2.1".to_string()),
+            },
+            timestamp: Utc::now() - Duration::minutes(age_mins),
+            description: "scan".to_string(),
+            raw_data: Value::Null,
+            indicators: vec![],
+            tags: vec![],
+            tenant_id: tenant.map(String::from),
+        }
+    }
+
+    fn ids(store: &EventStore, query: &str, range: TimeRange, tenant: Option<&str>) -> Vec<String> {
+        let query = HuntQuery::parse(query).unwrap();
+        store.search(&query, &range, tenant, &super::super::query::NoIntel).event_ids
+    }
+
+    fn last(minutes: i64) -> TimeRange {
+        TimeRange { start: Utc::now() - Duration::minutes(minutes), end: Utc::now() }
+    }
+
+    #[test]
+    fn test_orders_late_events_and_scopes_search() {
+        let store = EventStore::new();
+        store.append(&event("b", Some("acme"), 20), "default");
+        store.append(&event("d", None, 5), "default");
+        store.append(&event("a", Some("acme"), 40), "default");
+        store.append(&event("c", Some("globex"), 10), "default");
+
+        assert_eq!(ids(&store, "", last(60), None), vec!["a", "b", "c", "d"]);
+        assert_eq!(ids(&store, "", last(30), Some("acme")), vec!["b"]);
+        assert_eq!(ids(&store, "", last(60), Some("default")), vec!["d"]);
+        assert_eq!(ids(&store, "severity_level = 3 and severity >= high", last(60), Some("globex")), vec!["c"]);
+        assert_eq!(store.get_events(&["c".to_string()])[0].tenant_id.as_deref(), Some("globex"));
+    }
+
+    #[test]
+    fn test_retention_by_count_and_age() {
+        let store = EventStore::new().with_max_events(2).with_retention(Duration::hours(1));
+        store.append(&event("old", None, 90), "t");
+        assert!(store.is_empty());
+
+        store.append(&event("a", None, 3), "t");
+        store.append(&event("b", None, 2), "t");
+        store.append(&event("c", None, 1), "t");
+        assert_eq!(store.len(), 2);
+        assert_eq!(ids(&store, "", last(60), None), vec!["b", "c"]);
+        assert!(store.oldest().is_some_and(|t| t < Utc::now() - Duration::seconds(90)));
+    }
+}
@@ -4,8 +4,11 @@
 //! - SIEM integration (Splunk, Elastic, Sentinel, QRadar)
 //! - SOAR playbook automation
 //! - Case management
-//! - Threat hunting
-//! - Forensic collection
+//! - Threat hunting with a query language over stored events
+//! - Forensic collection with content-addressed storage and chain of custody
+//! - MITRE ATT&CK coverage scoring, gap reports and Navigator export
+//! - Alert notification with on-call schedules and escalation
+//! - Entity (user and host) risk scoring with a watchlist for access control
 //! - Compliance reporting
 //!
 //! # Architecture
@@ -45,6 +48,8 @@ pub mod soar;
 pub mod cases;
 pub mod hunting;
 pub mod forensics;
+pub mod mitre;
+pub mod ueba;
 pub mod compliance;
 pub mod alerts;
 pub mod normalize;
@@ -75,7 +80,7 @@ pub struct SecurityEvent {
     pub tenant_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum EventType {
     // Network events
     NetworkIntrusion,
@@ -225,15 +230,21 @@ pub struct SecurityOperationsPlatform {
     /// SOAR engine
     pub soar: soar::SoarEngine,
     /// Case management
-    pub cases: cases::CaseManager,
+    pub cases: Arc<cases::CaseManager>,
     /// Threat hunting
     pub hunting: hunting::ThreatHunter,
     /// Forensics
-    pub forensics: forensics::ForensicsCollector,
+    pub forensics: Arc<forensics::ForensicsCollector>,
     /// Compliance
     pub compliance: compliance::ComplianceEngine,
-    /// Alert router
-    pub alerts: alerts::AlertRouter,
+    /// Alert router, shared with its escalation task
+    pub alerts: Arc<alerts::AlertRouter>,
+    /// Event correlation
+    pub correlation: correlation::EventCorrelator,
+    /// ATT&CK detection coverage
+    pub mitre: Arc<mitre::MitreRegistry>,
+    /// User and host risk scores and the watchlist
+    pub entities: Arc<ueba::EntityAnalytics>,
     /// Event bus
     event_bus: EventBus,
     /// Config
@@ -269,14 +280,24 @@ type EventSubscriber = Arc<dyn Fn(&SecurityEvent) + Send + Sync>;
 
 impl SecurityOperationsPlatform {
     pub fn new(config: SopConfig) -> Self {
+        let cases = Arc::new(cases::CaseManager::new());
+        let forensics = Arc::new(forensics::ForensicsCollector::new().with_case_manager(cases.clone()));
+        let correlation = correlation::EventCorrelator::new();
+        let mitre = Arc::new(mitre::MitreRegistry::new());
+        mitre.sync_correlation_rules(&correlation);
         Self {
             siem: siem::SiemIntegration::new(),
-            soar: soar::SoarEngine::new(),
-            cases: cases::CaseManager::new(),
+            soar: soar::SoarEngine::new()
+                .with_case_sink(cases.clone())
+                .with_forensics(forensics.clone()),
+            cases,
             hunting: hunting::ThreatHunter::new(),
-            forensics: forensics::ForensicsCollector::new(),
+            forensics,
             compliance: compliance::ComplianceEngine::new(),
-            alerts: alerts::AlertRouter::new(),
+            alerts: Arc::new(alerts::AlertRouter::new()),
+            correlation,
+            mitre,
+            entities: Arc::new(ueba::EntityAnalytics::new()),
             event_bus: EventBus {
                 subscribers: dashmap::DashMap::new(),
             },
@@ -293,23 +314,46 @@ impl SecurityOperationsPlatform {
             self.siem.forward(&event).await;
         }
         
-        // Check if alert should be generated
-        if event.severity >= self.config.default_severity_threshold {
+        // Keep every event, duplicates included, for threat hunting
+        self.hunting.record_event(&event, &self.config.tenant_id);
+        
+        // Correlate; duplicates and noisy sources stop here
+        let correlated = self.correlation.correlate(&event);
+        if let Some(reason) = &correlated.suppressed {
+            tracing::debug!("Event {} suppressed: {:?}", event.id, reason);
+            self.notify_subscribers(&event);
+            return;
+        }
+        
+        // Owned before the first await, so the ingest future stays `Send`
+        // for `spawn_ingest`
+        let is_correlated = correlated.is_correlated();
+        let new_alerts: Vec<SecurityAlert> = correlated.new_alerts;
+        for alert in new_alerts {
+            self.raise_alert(&alert, &event).await;
+        }
+        
+        // Events already part of a correlated alert don't get their own
+        if !is_correlated && event.severity >= self.config.default_severity_threshold {
             let alert = self.create_alert(&event).await;
-            
-            // Route alert
-            self.alerts.route(&alert).await;
-            
-            // Trigger SOAR playbooks
-            if self.config.soar_enabled {
-                self.soar.trigger(&alert).await;
-            }
+            self.raise_alert(&alert, &event).await;
         }
         
         // Notify subscribers
         self.notify_subscribers(&event);
     }
     
+    async fn raise_alert(&self, alert: &SecurityAlert, event: &SecurityEvent) {
+        // Route alert
+        self.alerts.route(alert).await;
+        self.entities.record_alert(alert).await;
+        
+        // Trigger SOAR playbooks
+        if self.config.soar_enabled {
+            self.soar.trigger_for_event(alert, event).await;
+        }
+    }
+    
     async fn create_alert(&self, event: &SecurityEvent) -> SecurityAlert {
         let mut alert = SecurityAlert {
             id: Uuid::new_v4().to_string(),
@@ -332,7 +376,7 @@ impl SecurityOperationsPlatform {
         }
         
         // Map to MITRE ATT&CK
-        let (tactics, techniques) = self.map_to_mitre(&event.event_type);
+        let (tactics, techniques) = self.mitre.map_event_type(&event.event_type);
         alert.mitre_tactics = tactics;
         alert.mitre_techniques = techniques;
         
@@ -377,32 +421,6 @@ impl SecurityOperationsPlatform {
         score.min(100.0)
     }
     
-    fn map_to_mitre(&self, event_type: &EventType) -> (Vec<String>, Vec<String>) {
-        match event_type {
-            EventType::NetworkIntrusion => (
-                vec!["TA0001".to_string()], // Initial Access
-                vec!["T1190".to_string()], // Exploit Public-Facing Application
-            ),
-            EventType::BruteForceAttempt => (
-                vec!["TA0006".to_string()], // Credential Access
-                vec!["T1110".to_string()], // Brute Force
-            ),
-            EventType::PrivilegeEscalation => (
-                vec!["TA0004".to_string()], // Privilege Escalation
-                vec!["T1068".to_string()], // Exploitation for Privilege Escalation
-            ),
-            EventType::DataExfiltration => (
-                vec!["TA0010".to_string()], // Exfiltration
-                vec!["T1041".to_string()], // Exfiltration Over C2 Channel
-            ),
-            EventType::MalwareDetected => (
-                vec!["TA0002".to_string()], // Execution
-                vec!["T1204".to_string()], // User Execution
-            ),
-            _ => (vec![], vec![]),
-        }
-    }
-    
     fn notify_subscribers(&self, event: &SecurityEvent) {
         let event_type = format!("{:?}", event.event_type);
         if let Some(subs) = self.event_bus.subscribers.get(&event_type) {
@@ -412,6 +430,22 @@ impl SecurityOperationsPlatform {
         }
     }
     
+    /// Ingest events from a channel, e.g. one fed by a
+    /// [`normalize::SyslogListener`], until every sender is dropped
+    pub fn spawn_ingest(self: Arc<Self>, mut events: tokio::sync::mpsc::Receiver<SecurityEvent>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                self.ingest_event(event).await;
+            }
+        })
+    }
+    
+    /// Register a correlation rule and count it toward ATT&CK coverage
+    pub fn register_correlation_rule(&self, rule: correlation::CorrelationRule) {
+        self.correlation.register_rule(rule);
+        self.mitre.sync_correlation_rules(&self.correlation);
+    }
+
     /// Subscribe to events
     pub fn subscribe(&self, event_type: &str, handler: EventSubscriber) {
         self.event_bus.subscribers
@@ -420,6 +454,74 @@ impl SecurityOperationsPlatform {
             .push(handler);
     }
     
+    /// Run saved hunts that are due and route the alerts they raise
+    pub async fn run_due_hunts(&self) -> Vec<hunting::HuntRun> {
+        let runs = self.hunting.run_due_hunts();
+        for alert in runs.iter().filter_map(|r| r.alert.as_ref()) {
+            self.alerts.route(alert).await;
+            self.entities.record_alert(alert).await;
+            if self.config.soar_enabled {
+                self.soar.trigger(alert).await;
+            }
+        }
+        runs
+    }
+    
+    /// Record hunt results on a case, opening a new one unless `case_id`
+    /// names an existing case. Threat intel matches become IOC observables.
+    pub async fn export_hunt_to_case(
+        &self,
+        result: &hunting::HuntResult,
+        case_id: Option<&str>,
+        actor: &str,
+    ) -> Result<cases::Case, cases::CaseError> {
+        const EXPORTED_ROWS: usize = 20;
+        
+        let case_id = match case_id {
+            Some(id) => id.to_string(),
+            None => {
+                let title: String = result.query.chars().take(80).collect();
+                let severity = if result.intel_hits.is_empty() { Severity::Medium } else { Severity::High };
+                self.cases.create_case(
+                    &format!("Threat hunt: {}", title),
+                    &format!("Opened from hunt results for:\n{}", result.query),
+                    severity,
+                    cases::CaseType::SecurityIncident,
+                    actor,
+                ).await.id
+            }
+        };
+        
+        let mut summary = format!(
+            "Hunt `{}` returned {} rows from {} events",
+            result.query, result.rows.len(), result.event_ids.len(),
+        );
+        for row in result.rows.iter().take(EXPORTED_ROWS) {
+            summary.push('\n');
+            summary.push_str(&row.to_string());
+        }
+        if result.rows.len() > EXPORTED_ROWS {
+            summary.push_str(&format!("\n... {} more rows", result.rows.len() - EXPORTED_ROWS));
+        }
+        let mut case = self.cases.add_comment(&case_id, &summary, actor).await?;
+        
+        for hit in &result.intel_hits {
+            if case.observables.iter().any(|o| o.value == hit.value) {
+                continue;
+            }
+            let observable = cases::Observable {
+                id: Uuid::new_v4().to_string(),
+                observable_type: hit.indicator_type.into(),
+                value: hit.value.clone(),
+                tlp: cases::Tlp::Amber,
+                is_ioc: true,
+                tags: hit.feeds.clone(),
+            };
+            case = self.cases.add_observable(&case_id, observable, actor).await?;
+        }
+        Ok(case)
+    }
+    
     /// Get platform stats
     pub async fn get_stats(&self) -> PlatformStats {
         PlatformStats {
@@ -428,6 +530,7 @@ impl SecurityOperationsPlatform {
             cases_active: self.cases.get_active_count().await,
             playbooks_triggered: self.soar.get_execution_count().await,
             mean_time_to_respond: self.cases.get_mttr().await,
+            siem_delivery: self.siem.delivery_metrics(),
         }
     }
 }
@@ -439,4 +542,60 @@ pub struct PlatformStats {
     pub cases_active: u64,
     pub playbooks_triggered: u64,
     pub mean_time_to_respond: f64,
+    /// Delivery counters per SIEM destination
+    pub siem_delivery: Vec<siem::DeliveryMetrics>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn event(event_type: EventType, severity: Severity) -> SecurityEvent {
+        SecurityEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type,
+            severity,
+            source: EventSource {
+                system: "ngfw".to_string(),
+                component: "ids".to_string(),
+                host: Some("edge-1".to_string()),
+                ip: Some("10.0.0.1".to_string()),
+            },
+            timestamp: Utc::now(),
+            description: "test event".to_string(),
+            raw_data: serde_json::Value::Null,
+            indicators: vec![],
+            tags: vec![],
+            tenant_id: Some("acme".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_ingest_drains_channel() {
+        let platform = Arc::new(SecurityOperationsPlatform::new(SopConfig {
+            tenant_id: "acme".to_string(),
+            auto_enrichment: false,
+            ..Default::default()
+        }));
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        platform.subscribe("PortScan", Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let handle = platform.clone().spawn_ingest(rx);
+        tx.send(event(EventType::PortScan, Severity::Medium)).await.unwrap();
+        tx.send(event(EventType::PortScan, Severity::Medium)).await.unwrap();
+        drop(tx);
+
+        // Ends once every sender is gone
+        tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+            .await
+            .expect("ingest task did not stop")
+            .unwrap();
+        assert_eq!(seen.load(Ordering::Relaxed), 2);
+        assert!(platform.alerts.stats().total_received >= 1);
+    }
 }
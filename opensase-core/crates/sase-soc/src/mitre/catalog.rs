@@ -0,0 +1,426 @@
+//! ATT&CK technique catalog
+//!
+//! The built-in catalog holds every Enterprise ATT&CK tactic and technique
+//! (v15). Sub-techniques are not embedded; they resolve to their parent
+//! unless a STIX bundle with them has been loaded via
+//! [`Catalog::from_stix_bundle`].
+
+use super::MitreError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// ATT&CK release the built-in catalog follows
+pub const ATTACK_VERSION: &str = "15";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tactic {
+    /// `TA0001`
+    pub id: String,
+    pub name: String,
+    /// Kill chain phase name, e.g. `initial-access`
+    pub shortname: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Technique {
+    /// `T1110` or `T1110.004`
+    pub id: String,
+    pub name: String,
+    /// Tactic shortnames
+    pub tactics: Vec<String>,
+}
+
+impl Technique {
+    /// Parent technique ID of a sub-technique
+    pub fn parent_id(&self) -> Option<&str> {
+        self.id.split_once('.').map(|(parent, _)| parent)
+    }
+}
+
+/// Tactics and techniques of one ATT&CK domain
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    /// ATT&CK release, when known
+    version: Option<String>,
+    /// In kill chain order
+    tactics: Vec<Tactic>,
+    techniques: BTreeMap<String, Technique>,
+}
+
+const RECON: &str = "reconnaissance";
+const RESOURCE: &str = "resource-development";
+const INITIAL: &str = "initial-access";
+const EXECUTION: &str = "execution";
+const PERSISTENCE: &str = "persistence";
+const PRIVESC: &str = "privilege-escalation";
+const EVASION: &str = "defense-evasion";
+const CREDS: &str = "credential-access";
+const DISCOVERY: &str = "discovery";
+const LATERAL: &str = "lateral-movement";
+const COLLECTION: &str = "collection";
+const C2: &str = "command-and-control";
+const EXFIL: &str = "exfiltration";
+const IMPACT: &str = "impact";
+
+const TACTICS: &[(&str, &str, &str)] = &[
+    ("TA0043", "Reconnaissance", RECON),
+    ("TA0042", "Resource Development", RESOURCE),
+    ("TA0001", "Initial Access", INITIAL),
+    ("TA0002", "Execution", EXECUTION),
+    ("TA0003", "Persistence", PERSISTENCE),
+    ("TA0004", "Privilege Escalation", PRIVESC),
+    ("TA0005", "Defense Evasion", EVASION),
+    ("TA0006", "Credential Access", CREDS),
+    ("TA0007", "Discovery", DISCOVERY),
+    ("TA0008", "Lateral Movement", LATERAL),
+    ("TA0009", "Collection", COLLECTION),
+    ("TA0011", "Command and Control", C2),
+    ("TA0010", "Exfiltration", EXFIL),
+    ("TA0040", "Impact", IMPACT),
+];
+
+const TECHNIQUES: &[(&str, &str, &[&str])] = &[
+    // Reconnaissance
+    ("T1595", "Active Scanning", &[RECON]),
+    ("T1592", "Gather Victim Host Information", &[RECON]),
+    ("T1589", "Gather Victim Identity Information", &[RECON]),
+    ("T1590", "Gather Victim Network Information", &[RECON]),
+    ("T1591", "Gather Victim Org Information", &[RECON]),
+    ("T1598", "Phishing for Information", &[RECON]),
+    ("T1597", "Search Closed Sources", &[RECON]),
+    ("T1596", "Search Open Technical Databases", &[RECON]),
+    ("T1593", "Search Open Websites/Domains", &[RECON]),
+    ("T1594", "Search Victim-Owned Websites", &[RECON]),
+    // Resource Development
+    ("T1650", "Acquire Access", &[RESOURCE]),
+    ("T1583", "Acquire Infrastructure", &[RESOURCE]),
+    ("T1586", "Compromise Accounts", &[RESOURCE]),
+    ("T1584", "Compromise Infrastructure", &[RESOURCE]),
+    ("T1587", "Develop Capabilities", &[RESOURCE]),
+    ("T1585", "Establish Accounts", &[RESOURCE]),
+    ("T1588", "Obtain Capabilities", &[RESOURCE]),
+    ("T1608", "Stage Capabilities", &[RESOURCE]),
+    // Initial Access
+    ("T1659", "Content Injection", &[INITIAL, C2]),
+    ("T1189", "Drive-by Compromise", &[INITIAL]),
+    ("T1190", "Exploit Public-Facing Application", &[INITIAL]),
+    ("T1133", "External Remote Services", &[INITIAL, PERSISTENCE]),
+    ("T1200", "Hardware Additions", &[INITIAL]),
+    ("T1566", "Phishing", &[INITIAL]),
+    ("T1091", "Replication Through Removable Media", &[INITIAL, LATERAL]),
+    ("T1195", "Supply Chain Compromise", &[INITIAL]),
+    ("T1199", "Trusted Relationship", &[INITIAL]),
+    ("T1078", "Valid Accounts", &[INITIAL, PERSISTENCE, PRIVESC, EVASION]),
+    // Execution
+    ("T1651", "Cloud Administration Command", &[EXECUTION]),
+    ("T1059", "Command and Scripting Interpreter", &[EXECUTION]),
+    ("T1609", "Container Administration Command", &[EXECUTION]),
+    ("T1610", "Deploy Container", &[EXECUTION, EVASION]),
+    ("T1203", "Exploitation for Client Execution", &[EXECUTION]),
+    ("T1559", "Inter-Process Communication", &[EXECUTION]),
+    ("T1106", "Native API", &[EXECUTION]),
+    ("T1053", "Scheduled Task/Job", &[EXECUTION, PERSISTENCE, PRIVESC]),
+    ("T1648", "Serverless Execution", &[EXECUTION]),
+    ("T1129", "Shared Modules", &[EXECUTION]),
+    ("T1072", "Software Deployment Tools", &[EXECUTION, LATERAL]),
+    ("T1569", "System Services", &[EXECUTION]),
+    ("T1204", "User Execution", &[EXECUTION]),
+    ("T1047", "Windows Management Instrumentation", &[EXECUTION]),
+    // Persistence
+    ("T1098", "Account Manipulation", &[PERSISTENCE, PRIVESC]),
+    ("T1197", "BITS Jobs", &[PERSISTENCE, EVASION]),
+    ("T1547", "Boot or Logon Autostart Execution", &[PERSISTENCE, PRIVESC]),
+    ("T1037", "Boot or Logon Initialization Scripts", &[PERSISTENCE, PRIVESC]),
+    ("T1176", "Browser Extensions", &[PERSISTENCE]),
+    ("T1554", "Compromise Host Software Binary", &[PERSISTENCE]),
+    ("T1136", "Create Account", &[PERSISTENCE]),
+    ("T1543", "Create or Modify System Process", &[PERSISTENCE, PRIVESC]),
+    ("T1546", "Event Triggered Execution", &[PERSISTENCE, PRIVESC]),
+    ("T1574", "Hijack Execution Flow", &[PERSISTENCE, PRIVESC, EVASION]),
+    ("T1525", "Implant Internal Image", &[PERSISTENCE]),
+    ("T1556", "Modify Authentication Process", &[CREDS, EVASION, PERSISTENCE]),
+    ("T1137", "Office Application Startup", &[PERSISTENCE]),
+    ("T1653", "Power Settings", &[PERSISTENCE]),
+    ("T1542", "Pre-OS Boot", &[EVASION, PERSISTENCE]),
+    ("T1505", "Server Software Component", &[PERSISTENCE]),
+    ("T1205", "Traffic Signaling", &[EVASION, PERSISTENCE, C2]),
+    // Privilege Escalation
+    ("T1548", "Abuse Elevation Control Mechanism", &[PRIVESC, EVASION]),
+    ("T1134", "Access Token Manipulation", &[EVASION, PRIVESC]),
+    ("T1484", "Domain or Tenant Policy Modification", &[EVASION, PRIVESC]),
+    ("T1611", "Escape to Host", &[PRIVESC]),
+    ("T1068", "Exploitation for Privilege Escalation", &[PRIVESC]),
+    ("T1055", "Process Injection", &[EVASION, PRIVESC]),
+    // Defense Evasion
+    ("T1612", "Build Image on Host", &[EVASION]),
+    ("T1622", "Debugger Evasion", &[EVASION, DISCOVERY]),
+    ("T1140", "Deobfuscate/Decode Files or Information", &[EVASION]),
+    ("T1006", "Direct Volume Access", &[EVASION]),
+    ("T1480", "Execution Guardrails", &[EVASION]),
+    ("T1211", "Exploitation for Defense Evasion", &[EVASION]),
+    ("T1222", "File and Directory Permissions Modification", &[EVASION]),
+    ("T1564", "Hide Artifacts", &[EVASION]),
+    ("T1562", "Impair Defenses", &[EVASION]),
+    ("T1656", "Impersonation", &[EVASION]),
+    ("T1070", "Indicator Removal", &[EVASION]),
+    ("T1202", "Indirect Command Execution", &[EVASION]),
+    ("T1036", "Masquerading", &[EVASION]),
+    ("T1578", "Modify Cloud Compute Infrastructure", &[EVASION]),
+    ("T1112", "Modify Registry", &[EVASION]),
+    ("T1601", "Modify System Image", &[EVASION]),
+    ("T1599", "Network Boundary Bridging", &[EVASION]),
+    ("T1027", "Obfuscated Files or Information", &[EVASION]),
+    ("T1647", "Plist File Modification", &[EVASION]),
+    ("T1620", "Reflective Code Loading", &[EVASION]),
+    ("T1207", "Rogue Domain Controller", &[EVASION]),
+    ("T1014", "Rootkit", &[EVASION]),
+    ("T1553", "Subvert Trust Controls", &[EVASION]),
+    ("T1218", "System Binary Proxy Execution", &[EVASION]),
+    ("T1216", "System Script Proxy Execution", &[EVASION]),
+    ("T1221", "Template Injection", &[EVASION]),
+    ("T1127", "Trusted Developer Utilities Proxy Execution", &[EVASION]),
+    ("T1535", "Unused/Unsupported Cloud Regions", &[EVASION]),
+    ("T1550", "Use Alternate Authentication Material", &[EVASION, LATERAL]),
+    ("T1497", "Virtualization/Sandbox Evasion", &[EVASION, DISCOVERY]),
+    ("T1600", "Weaken Encryption", &[EVASION]),
+    ("T1220", "XSL Script Processing", &[EVASION]),
+    // Credential Access
+    ("T1557", "Adversary-in-the-Middle", &[CREDS, COLLECTION]),
+    ("T1110", "Brute Force", &[CREDS]),
+    ("T1555", "Credentials from Password Stores", &[CREDS]),
+    ("T1212", "Exploitation for Credential Access", &[CREDS]),
+    ("T1187", "Forced Authentication", &[CREDS]),
+    ("T1606", "Forge Web Credentials", &[CREDS]),
+    ("T1056", "Input Capture", &[COLLECTION, CREDS]),
+    ("T1111", "Multi-Factor Authentication Interception", &[CREDS]),
+    ("T1621", "Multi-Factor Authentication Request Generation", &[CREDS]),
+    ("T1040", "Network Sniffing", &[CREDS, DISCOVERY]),
+    ("T1003", "OS Credential Dumping", &[CREDS]),
+    ("T1528", "Steal Application Access Token", &[CREDS]),
+    ("T1649", "Steal or Forge Authentication Certificates", &[CREDS]),
+    ("T1558", "Steal or Forge Kerberos Tickets", &[CREDS]),
+    ("T1539", "Steal Web Session Cookie", &[CREDS]),
+    ("T1552", "Unsecured Credentials", &[CREDS]),
+    // Discovery
+    ("T1087", "Account Discovery", &[DISCOVERY]),
+    ("T1010", "Application Window Discovery", &[DISCOVERY]),
+    ("T1217", "Browser Information Discovery", &[DISCOVERY]),
+    ("T1580", "Cloud Infrastructure Discovery", &[DISCOVERY]),
+    ("T1538", "Cloud Service Dashboard", &[DISCOVERY]),
+    ("T1526", "Cloud Service Discovery", &[DISCOVERY]),
+    ("T1619", "Cloud Storage Object Discovery", &[DISCOVERY]),
+    ("T1613", "Container and Resource Discovery", &[DISCOVERY]),
+    ("T1652", "Device Driver Discovery", &[DISCOVERY]),
+    ("T1482", "Domain Trust Discovery", &[DISCOVERY]),
+    ("T1083", "File and Directory Discovery", &[DISCOVERY]),
+    ("T1615", "Group Policy Discovery", &[DISCOVERY]),
+    ("T1654", "Log Enumeration", &[DISCOVERY]),
+    ("T1046", "Network Service Discovery", &[DISCOVERY]),
+    ("T1135", "Network Share Discovery", &[DISCOVERY]),
+    ("T1201", "Password Policy Discovery", &[DISCOVERY]),
+    ("T1120", "Peripheral Device Discovery", &[DISCOVERY]),
+    ("T1069", "Permission Groups Discovery", &[DISCOVERY]),
+    ("T1057", "Process Discovery", &[DISCOVERY]),
+    ("T1012", "Query Registry", &[DISCOVERY]),
+    ("T1018", "Remote System Discovery", &[DISCOVERY]),
+    ("T1518", "Software Discovery", &[DISCOVERY]),
+    ("T1082", "System Information Discovery", &[DISCOVERY]),
+    ("T1614", "System Location Discovery", &[DISCOVERY]),
+    ("T1016", "System Network Configuration Discovery", &[DISCOVERY]),
+    ("T1049", "System Network Connections Discovery", &[DISCOVERY]),
+    ("T1033", "System Owner/User Discovery", &[DISCOVERY]),
+    ("T1007", "System Service Discovery", &[DISCOVERY]),
+    ("T1124", "System Time Discovery", &[DISCOVERY]),
+    // Lateral Movement
+    ("T1210", "Exploitation of Remote Services", &[LATERAL]),
+    ("T1534", "Internal Spearphishing", &[LATERAL]),
+    ("T1570", "Lateral Tool Transfer", &[LATERAL]),
+    ("T1563", "Remote Service Session Hijacking", &[LATERAL]),
+    ("T1021", "Remote Services", &[LATERAL]),
+    ("T1080", "Taint Shared Content", &[LATERAL]),
+    // Collection
+    ("T1560", "Archive Collected Data", &[COLLECTION]),
+    ("T1123", "Audio Capture", &[COLLECTION]),
+    ("T1119", "Automated Collection", &[COLLECTION]),
+    ("T1185", "Browser Session Hijacking", &[COLLECTION]),
+    ("T1115", "Clipboard Data", &[COLLECTION]),
+    ("T1530", "Data from Cloud Storage", &[COLLECTION]),
+    ("T1602", "Data from Configuration Repository", &[COLLECTION]),
+    ("T1213", "Data from Information Repositories", &[COLLECTION]),
+    ("T1005", "Data from Local System", &[COLLECTION]),
+    ("T1039", "Data from Network Shared Drive", &[COLLECTION]),
+    ("T1025", "Data from Removable Media", &[COLLECTION]),
+    ("T1074", "Data Staged", &[COLLECTION]),
+    ("T1114", "Email Collection", &[COLLECTION]),
+    ("T1113", "Screen Capture", &[COLLECTION]),
+    ("T1125", "Video Capture", &[COLLECTION]),
+    // Command and Control
+    ("T1071", "Application Layer Protocol", &[C2]),
+    ("T1092", "Communication Through Removable Media", &[C2]),
+    ("T1132", "Data Encoding", &[C2]),
+    ("T1001", "Data Obfuscation", &[C2]),
+    ("T1568", "Dynamic Resolution", &[C2]),
+    ("T1573", "Encrypted Channel", &[C2]),
+    ("T1008", "Fallback Channels", &[C2]),
+    ("T1105", "Ingress Tool Transfer", &[C2]),
+    ("T1104", "Multi-Stage Channels", &[C2]),
+    ("T1095", "Non-Application Layer Protocol", &[C2]),
+    ("T1571", "Non-Standard Port", &[C2]),
+    ("T1572", "Protocol Tunneling", &[C2]),
+    ("T1090", "Proxy", &[C2]),
+    ("T1219", "Remote Access Software", &[C2]),
+    ("T1102", "Web Service", &[C2]),
+    // Exfiltration
+    ("T1020", "Automated Exfiltration", &[EXFIL]),
+    ("T1030", "Data Transfer Size Limits", &[EXFIL]),
+    ("T1048", "Exfiltration Over Alternative Protocol", &[EXFIL]),
+    ("T1041", "Exfiltration Over C2 Channel", &[EXFIL]),
+    ("T1011", "Exfiltration Over Other Network Medium", &[EXFIL]),
+    ("T1052", "Exfiltration Over Physical Medium", &[EXFIL]),
+    ("T1567", "Exfiltration Over Web Service", &[EXFIL]),
+    ("T1029", "Scheduled Transfer", &[EXFIL]),
+    ("T1537", "Transfer Data to Cloud Account", &[EXFIL]),
+    // Impact
+    ("T1531", "Account Access Removal", &[IMPACT]),
+    ("T1485", "Data Destruction", &[IMPACT]),
+    ("T1486", "Data Encrypted for Impact", &[IMPACT]),
+    ("T1565", "Data Manipulation", &[IMPACT]),
+    ("T1491", "Defacement", &[IMPACT]),
+    ("T1561", "Disk Wipe", &[IMPACT]),
+    ("T1499", "Endpoint Denial of Service", &[IMPACT]),
+    ("T1657", "Financial Theft", &[IMPACT]),
+    ("T1495", "Firmware Corruption", &[IMPACT]),
+    ("T1490", "Inhibit System Recovery", &[IMPACT]),
+    ("T1498", "Network Denial of Service", &[IMPACT]),
+    ("T1496", "Resource Hijacking", &[IMPACT]),
+    ("T1489", "Service Stop", &[IMPACT]),
+    ("T1529", "System Shutdown/Reboot", &[IMPACT]),
+];
+
+impl Catalog {
+    /// Built-in Enterprise ATT&CK catalog
+    pub fn enterprise() -> Self {
+        let tactics = TACTICS.iter()
+            .map(|(id, name, shortname)| Tactic {
+                id: id.to_string(),
+                name: name.to_string(),
+                shortname: shortname.to_string(),
+            })
+            .collect();
+        let techniques = TECHNIQUES.iter()
+            .map(|(id, name, tactics)| (id.to_string(), Technique {
+                id: id.to_string(),
+                name: name.to_string(),
+                tactics: tactics.iter().map(|t| t.to_string()).collect(),
+            }))
+            .collect();
+        Self { version: Some(ATTACK_VERSION.to_string()), tactics, techniques }
+    }
+
+    /// Catalog from an ATT&CK STIX 2.x bundle (e.g. `enterprise-attack.json`),
+    /// sub-techniques included; revoked and deprecated objects are skipped
+    pub fn from_stix_bundle(json: &str) -> Result<Self, MitreError> {
+        let bundle: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| MitreError::InvalidBundle(e.to_string()))?;
+        let objects = bundle.get("objects")
+            .and_then(|o| o.as_array())
+            .ok_or_else(|| MitreError::InvalidBundle("No objects in bundle".into()))?;
+
+        let attack_id = |object: &serde_json::Value| -> Option<String> {
+            object.get("external_references")?.as_array()?
+                .iter()
+                .find(|r| r.get("source_name").and_then(|s| s.as_str()) == Some("mitre-attack"))?
+                .get("external_id")?.as_str()
+                .map(String::from)
+        };
+        let flag = |object: &serde_json::Value, key: &str| object.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let mut catalog = Self::default();
+        let mut tactics = Vec::new();
+        for object in objects {
+            if flag(object, "revoked") || flag(object, "x_mitre_deprecated") {
+                continue;
+            }
+            let kind = object.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+            let name = object.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+            if kind == "x-mitre-collection" {
+                catalog.version = object.get("x_mitre_version").and_then(|v| v.as_str()).map(String::from);
+                continue;
+            }
+            let Some(id) = attack_id(object) else { continue };
+
+            match kind {
+                "x-mitre-tactic" => {
+                    let shortname = object.get("x_mitre_shortname").and_then(|s| s.as_str()).unwrap_or_default();
+                    tactics.push(Tactic { id, name, shortname: shortname.to_string() });
+                }
+                "attack-pattern" => {
+                    let phases = object.get("kill_chain_phases")
+                        .and_then(|p| p.as_array())
+                        .map(|phases| phases.iter()
+                            .filter(|p| p.get("kill_chain_name").and_then(|k| k.as_str()) == Some("mitre-attack"))
+                            .filter_map(|p| p.get("phase_name").and_then(|n| n.as_str()).map(String::from))
+                            .collect())
+                        .unwrap_or_default();
+                    catalog.techniques.insert(id.clone(), Technique { id, name, tactics: phases });
+                }
+                _ => {}
+            }
+        }
+
+        if tactics.is_empty() || catalog.techniques.is_empty() {
+            return Err(MitreError::InvalidBundle("Bundle has no ATT&CK tactics or techniques".into()));
+        }
+        // Keep kill chain order for tactics the built-in catalog knows
+        let order = |t: &Tactic| TACTICS.iter().position(|(id, ..)| *id == t.id).unwrap_or(usize::MAX);
+        tactics.sort_by_key(|t| (order(t), t.id.clone()));
+        catalog.tactics = tactics;
+        Ok(catalog)
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn tactics(&self) -> &[Tactic] {
+        &self.tactics
+    }
+
+    /// Tactic by ID (`TA0006`) or shortname (`credential-access`)
+    pub fn tactic(&self, id_or_shortname: &str) -> Option<&Tactic> {
+        self.tactics.iter().find(|t| t.id == id_or_shortname || t.shortname == id_or_shortname)
+    }
+
+    pub fn technique(&self, id: &str) -> Option<&Technique> {
+        self.techniques.get(id)
+    }
+
+    /// Technique, or for an unlisted sub-technique its parent
+    pub fn resolve(&self, id: &str) -> Option<&Technique> {
+        self.techniques.get(id)
+            .or_else(|| id.split_once('.').and_then(|(parent, _)| self.techniques.get(parent)))
+    }
+
+    /// Techniques that aren't sub-techniques
+    pub fn techniques(&self) -> impl Iterator<Item = &Technique> {
+        self.techniques.values().filter(|t| t.parent_id().is_none())
+    }
+
+    /// Sub-techniques of a technique
+    pub fn sub_techniques<'a>(&'a self, parent: &'a str) -> impl Iterator<Item = &'a Technique> + 'a {
+        self.techniques.values().filter(move |t| t.parent_id() == Some(parent))
+    }
+
+    /// Techniques (not sub-techniques) under a tactic shortname
+    pub fn techniques_for_tactic<'a>(&'a self, shortname: &'a str) -> impl Iterator<Item = &'a Technique> + 'a {
+        self.techniques().filter(move |t| t.tactics.iter().any(|s| s == shortname))
+    }
+
+    pub fn len(&self) -> usize {
+        self.techniques.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.techniques.is_empty()
+    }
+}
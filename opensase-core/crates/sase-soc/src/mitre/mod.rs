@@ -0,0 +1,797 @@
+//! MITRE ATT&CK Coverage
+//!
+//! Tracks which ATT&CK techniques the platform can detect. Anything that
+//! raises alerts (correlation rules, IDS signatures, DLP policies, anomaly
+//! models in other crates) registers a [`Detector`] listing the techniques
+//! it covers. The registry scores coverage per technique and tactic,
+//! reports gaps, and exports an ATT&CK Navigator layer.
+//!
+//! A technique's score is the chance at least one of its detectors fires,
+//! `1 - Π(1 - confidence)` over its enabled detectors. Detectors for a
+//! sub-technique credit the parent at half their confidence. A tactic
+//! scores the mean of its techniques, scaled to 0-100.
+
+mod catalog;
+
+pub use catalog::{Catalog, Tactic, Technique, ATTACK_VERSION};
+
+use crate::correlation::{CorrelationLogic, EventCorrelator};
+use crate::EventType;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Source name of detectors synced from the event correlator
+const CORRELATION_SOURCE: &str = "sase-soc.correlation";
+/// Navigator layer format version
+const LAYER_VERSION: &str = "4.5";
+const NAVIGATOR_VERSION: &str = "4.9.1";
+
+pub struct MitreRegistry {
+    catalog: parking_lot::RwLock<Arc<Catalog>>,
+    detectors: dashmap::DashMap<String, Detector>,
+    /// Tactics and techniques tagged on alerts raised straight from an event
+    event_mappings: dashmap::DashMap<EventType, TechniqueMapping>,
+}
+
+/// Something that raises alerts for one or more techniques
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Detector {
+    pub id: String,
+    pub name: String,
+    /// Crate or service that owns the detector, e.g. `sase-ips`
+    pub source: String,
+    pub kind: DetectorKind,
+    /// Technique or sub-technique IDs, e.g. `T1110` or `T1110.004`
+    pub techniques: Vec<String>,
+    /// How reliably the detector fires on the technique, 0.0-1.0
+    pub confidence: f64,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorKind {
+    CorrelationRule,
+    Signature,
+    Anomaly,
+    Hunt,
+    Policy,
+    Other,
+}
+
+impl Detector {
+    pub fn new(id: &str, name: &str, source: &str, kind: DetectorKind) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            source: source.to_string(),
+            kind,
+            techniques: vec![],
+            confidence: 0.5,
+            enabled: true,
+        }
+    }
+
+    pub fn with_techniques<I, S>(mut self, techniques: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.techniques.extend(techniques.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TechniqueMapping {
+    /// Tactic IDs, e.g. `TA0006`
+    pub tactics: Vec<String>,
+    pub techniques: Vec<String>,
+}
+
+// =============================================================================
+// Reports
+// =============================================================================
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TechniqueCoverage {
+    pub technique_id: String,
+    pub name: String,
+    /// Tactic shortnames
+    pub tactics: Vec<String>,
+    /// 0.0-1.0
+    pub score: f64,
+    pub detectors: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TacticCoverage {
+    pub tactic_id: String,
+    pub name: String,
+    pub shortname: String,
+    pub techniques: usize,
+    pub covered: usize,
+    /// 0-100
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub generated_at: DateTime<Utc>,
+    pub attack_version: Option<String>,
+    /// Mean technique score across the catalog, 0-100
+    pub overall_score: f64,
+    pub techniques_total: usize,
+    pub techniques_covered: usize,
+    pub detectors: usize,
+    pub tactics: Vec<TacticCoverage>,
+    /// Covered techniques and directly detected sub-techniques
+    pub techniques: Vec<TechniqueCoverage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GapReport {
+    pub generated_at: DateTime<Utc>,
+    /// Techniques scoring below this are reported as weak
+    pub threshold: f64,
+    pub uncovered_total: usize,
+    pub weak_total: usize,
+    /// Tactics with gaps, in kill chain order
+    pub tactics: Vec<TacticGaps>,
+    /// Detector techniques missing from the catalog
+    pub unresolved: Vec<UnresolvedTechnique>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TacticGaps {
+    pub tactic_id: String,
+    pub name: String,
+    pub score: f64,
+    pub uncovered: Vec<TechniqueRef>,
+    pub weak: Vec<TechniqueCoverage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TechniqueRef {
+    pub technique_id: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnresolvedTechnique {
+    pub detector_id: String,
+    pub technique_id: String,
+}
+
+/// Per-technique detection tally
+struct Tally {
+    /// Chance every detector misses
+    miss: f64,
+    detectors: Vec<String>,
+}
+
+impl Tally {
+    fn score(&self) -> f64 {
+        1.0 - self.miss
+    }
+}
+
+// =============================================================================
+// Registry
+// =============================================================================
+
+impl MitreRegistry {
+    /// Registry over the built-in Enterprise catalog with default event
+    /// type mappings
+    pub fn new() -> Self {
+        let registry = Self {
+            catalog: parking_lot::RwLock::new(Arc::new(Catalog::enterprise())),
+            detectors: dashmap::DashMap::new(),
+            event_mappings: dashmap::DashMap::new(),
+        };
+        registry.load_default_mappings();
+        registry
+    }
+
+    fn load_default_mappings(&self) {
+        let defaults: &[(EventType, &str, &str)] = &[
+            (EventType::NetworkIntrusion, "TA0001", "T1190"), // Exploit Public-Facing Application
+            (EventType::DdosAttack, "TA0040", "T1498"), // Network Denial of Service
+            (EventType::PortScan, "TA0007", "T1046"), // Network Service Discovery
+            (EventType::SuspiciousTraffic, "TA0011", "T1071"), // Application Layer Protocol
+            (EventType::MalwareDetected, "TA0002", "T1204"), // User Execution
+            (EventType::SuspiciousProcess, "TA0002", "T1059"), // Command and Scripting Interpreter
+            (EventType::FileIntegrity, "TA0040", "T1565"), // Data Manipulation
+            (EventType::PrivilegeEscalation, "TA0004", "T1068"), // Exploitation for Privilege Escalation
+            (EventType::BruteForceAttempt, "TA0006", "T1110"), // Brute Force
+            (EventType::ImpossibleTravel, "TA0001", "T1078"), // Valid Accounts
+            (EventType::AccountCompromise, "TA0001", "T1078"), // Valid Accounts
+            (EventType::UnauthorizedAccess, "TA0001", "T1078"), // Valid Accounts
+            (EventType::DataExfiltration, "TA0010", "T1041"), // Exfiltration Over C2 Channel
+            (EventType::DlpViolation, "TA0010", "T1567"), // Exfiltration Over Web Service
+            (EventType::WebAttack, "TA0001", "T1190"), // Exploit Public-Facing Application
+            (EventType::ApiAbuse, "TA0001", "T1190"), // Exploit Public-Facing Application
+            (EventType::BotActivity, "TA0006", "T1110.004"), // Credential Stuffing
+        ];
+        for (event_type, tactic, technique) in defaults {
+            self.event_mappings.insert(*event_type, TechniqueMapping {
+                tactics: vec![tactic.to_string()],
+                techniques: vec![technique.to_string()],
+            });
+        }
+    }
+
+    pub fn catalog(&self) -> Arc<Catalog> {
+        self.catalog.read().clone()
+    }
+
+    /// Swap in another catalog, e.g. one loaded from a newer STIX bundle.
+    /// Registered detectors are kept; techniques the new catalog lacks show
+    /// up as unresolved in gap reports.
+    pub fn load_catalog(&self, catalog: Catalog) {
+        tracing::info!(
+            "Loaded ATT&CK catalog {} with {} techniques",
+            catalog.version().unwrap_or("(unversioned)"),
+            catalog.len()
+        );
+        *self.catalog.write() = Arc::new(catalog);
+    }
+
+    // =========================================================================
+    // Detectors
+    // =========================================================================
+
+    /// Register or replace a detector
+    pub fn register_detector(&self, mut detector: Detector) -> Result<(), MitreError> {
+        if detector.techniques.is_empty() {
+            return Err(MitreError::InvalidDetector(format!("{} lists no techniques", detector.id)));
+        }
+        if !(0.0..=1.0).contains(&detector.confidence) {
+            return Err(MitreError::InvalidDetector(format!(
+                "{} confidence {} outside 0.0-1.0", detector.id, detector.confidence
+            )));
+        }
+
+        let catalog = self.catalog();
+        for technique in detector.techniques.iter_mut() {
+            *technique = technique.trim().to_ascii_uppercase();
+            if catalog.resolve(technique).is_none() {
+                return Err(MitreError::UnknownTechnique(technique.clone()));
+            }
+        }
+        detector.techniques.sort();
+        detector.techniques.dedup();
+
+        self.detectors.insert(detector.id.clone(), detector);
+        Ok(())
+    }
+
+    pub fn unregister_detector(&self, id: &str) -> Option<Detector> {
+        self.detectors.remove(id).map(|(_, d)| d)
+    }
+
+    pub fn detector(&self, id: &str) -> Option<Detector> {
+        self.detectors.get(id).map(|d| d.clone())
+    }
+
+    pub fn detectors(&self) -> Vec<Detector> {
+        let mut detectors: Vec<Detector> = self.detectors.iter().map(|d| d.clone()).collect();
+        detectors.sort_by(|a, b| a.id.cmp(&b.id));
+        detectors
+    }
+
+    /// Detectors covering a technique, including those for its sub-techniques
+    pub fn detectors_for(&self, technique_id: &str) -> Vec<Detector> {
+        let sub_prefix = format!("{}.", technique_id);
+        let mut detectors: Vec<Detector> = self.detectors.iter()
+            .filter(|d| d.techniques.iter().any(|t| t == technique_id || t.starts_with(&sub_prefix)))
+            .map(|d| d.clone())
+            .collect();
+        detectors.sort_by(|a, b| a.id.cmp(&b.id));
+        detectors
+    }
+
+    /// Mirror the correlator's rules as detectors, dropping detectors of
+    /// rules that no longer exist. Returns the number of rules registered.
+    pub fn sync_correlation_rules(&self, correlator: &EventCorrelator) -> usize {
+        let rules = correlator.rules();
+        let ids: Vec<String> = rules.iter().map(|r| format!("correlation:{}", r.id)).collect();
+        self.detectors.retain(|id, d| d.source != CORRELATION_SOURCE || ids.contains(id));
+
+        let mut registered = 0;
+        for (rule, id) in rules.into_iter().zip(ids) {
+            if rule.mitre_techniques.is_empty() {
+                self.detectors.remove(&id);
+                continue;
+            }
+            // Multi-stage sequences are more specific than a single threshold
+            let confidence = match rule.logic {
+                CorrelationLogic::Threshold { .. } => 0.6,
+                CorrelationLogic::Sequence { .. } => 0.8,
+            };
+            let mut detector = Detector::new(&id, &rule.name, CORRELATION_SOURCE, DetectorKind::CorrelationRule)
+                .with_techniques(rule.mitre_techniques)
+                .with_confidence(confidence);
+            detector.enabled = rule.enabled;
+
+            match self.register_detector(detector) {
+                Ok(()) => registered += 1,
+                Err(e) => tracing::warn!("Correlation rule {} not mapped to ATT&CK: {}", rule.id, e),
+            }
+        }
+        registered
+    }
+
+    // =========================================================================
+    // Event type mappings
+    // =========================================================================
+
+    /// Tactics and techniques for an alert raised straight from an event
+    pub fn map_event_type(&self, event_type: &EventType) -> (Vec<String>, Vec<String>) {
+        self.event_mappings.get(event_type)
+            .map(|m| (m.tactics.clone(), m.techniques.clone()))
+            .unwrap_or_default()
+    }
+
+    pub fn set_event_mapping(&self, event_type: EventType, mapping: TechniqueMapping) -> Result<(), MitreError> {
+        let catalog = self.catalog();
+        if let Some(tactic) = mapping.tactics.iter().find(|t| catalog.tactic(t).is_none()) {
+            return Err(MitreError::UnknownTactic(tactic.clone()));
+        }
+        if let Some(technique) = mapping.techniques.iter().find(|t| catalog.resolve(t).is_none()) {
+            return Err(MitreError::UnknownTechnique(technique.clone()));
+        }
+        self.event_mappings.insert(event_type, mapping);
+        Ok(())
+    }
+
+    pub fn remove_event_mapping(&self, event_type: &EventType) -> Option<TechniqueMapping> {
+        self.event_mappings.remove(event_type).map(|(_, m)| m)
+    }
+
+    // =========================================================================
+    // Coverage
+    // =========================================================================
+
+    fn tally(&self, catalog: &Catalog) -> (HashMap<String, Tally>, Vec<UnresolvedTechnique>) {
+        let mut tallies: HashMap<String, Tally> = HashMap::new();
+        let mut unresolved = Vec::new();
+
+        for detector in self.detectors() {
+            if !detector.enabled {
+                continue;
+            }
+            // Strongest contribution of this detector per technique, so one
+            // listing both T1110 and T1110.004 isn't counted twice for T1110
+            let mut contributions: BTreeMap<&str, f64> = BTreeMap::new();
+            for technique in &detector.techniques {
+                let Some(resolved) = catalog.resolve(technique) else {
+                    unresolved.push(UnresolvedTechnique {
+                        detector_id: detector.id.clone(),
+                        technique_id: technique.clone(),
+                    });
+                    continue;
+                };
+                match technique.split_once('.') {
+                    Some((parent, _)) => {
+                        if resolved.id != *parent {
+                            // Catalog lists the sub-technique itself
+                            let entry = contributions.entry(technique.as_str()).or_default();
+                            *entry = entry.max(detector.confidence);
+                        }
+                        let entry = contributions.entry(parent).or_default();
+                        *entry = entry.max(detector.confidence * 0.5);
+                    }
+                    None => {
+                        let entry = contributions.entry(technique.as_str()).or_default();
+                        *entry = entry.max(detector.confidence);
+                    }
+                }
+            }
+            for (technique, confidence) in contributions {
+                let tally = tallies.entry(technique.to_string()).or_insert_with(|| Tally { miss: 1.0, detectors: vec![] });
+                tally.miss *= 1.0 - confidence;
+                tally.detectors.push(detector.id.clone());
+            }
+        }
+        (tallies, unresolved)
+    }
+
+    fn technique_coverage(technique: &Technique, tally: Option<&Tally>) -> TechniqueCoverage {
+        TechniqueCoverage {
+            technique_id: technique.id.clone(),
+            name: technique.name.clone(),
+            tactics: technique.tactics.clone(),
+            score: tally.map(Tally::score).unwrap_or(0.0),
+            detectors: tally.map(|t| t.detectors.clone()).unwrap_or_default(),
+        }
+    }
+
+    pub fn coverage(&self) -> CoverageReport {
+        let catalog = self.catalog();
+        let (tallies, _) = self.tally(&catalog);
+
+        let tactics = catalog.tactics().iter()
+            .map(|tactic| {
+                let scores: Vec<f64> = catalog.techniques_for_tactic(&tactic.shortname)
+                    .map(|t| tallies.get(&t.id).map(Tally::score).unwrap_or(0.0))
+                    .collect();
+                TacticCoverage {
+                    tactic_id: tactic.id.clone(),
+                    name: tactic.name.clone(),
+                    shortname: tactic.shortname.clone(),
+                    techniques: scores.len(),
+                    covered: scores.iter().filter(|s| **s > 0.0).count(),
+                    score: mean(&scores) * 100.0,
+                }
+            })
+            .collect();
+
+        let all: Vec<f64> = catalog.techniques()
+            .map(|t| tallies.get(&t.id).map(Tally::score).unwrap_or(0.0))
+            .collect();
+
+        let mut techniques: Vec<TechniqueCoverage> = tallies.iter()
+            .filter_map(|(id, tally)| catalog.technique(id).map(|t| Self::technique_coverage(t, Some(tally))))
+            .collect();
+        techniques.sort_by(|a, b| a.technique_id.cmp(&b.technique_id));
+
+        CoverageReport {
+            generated_at: Utc::now(),
+            attack_version: catalog.version().map(String::from),
+            overall_score: mean(&all) * 100.0,
+            techniques_total: all.len(),
+            techniques_covered: all.iter().filter(|s| **s > 0.0).count(),
+            detectors: self.detectors.iter().filter(|d| d.enabled).count(),
+            tactics,
+            techniques,
+        }
+    }
+
+    /// Uncovered techniques, and covered ones scoring below `threshold`
+    /// (0.0-1.0), per tactic
+    pub fn gaps(&self, threshold: f64) -> GapReport {
+        let catalog = self.catalog();
+        let (tallies, unresolved) = self.tally(&catalog);
+
+        let mut tactics = Vec::new();
+        let (mut uncovered_total, mut weak_total) = (0, 0);
+        for tactic in catalog.tactics() {
+            let mut uncovered = Vec::new();
+            let mut weak = Vec::new();
+            let mut scores = Vec::new();
+            for technique in catalog.techniques_for_tactic(&tactic.shortname) {
+                let tally = tallies.get(&technique.id);
+                let score = tally.map(Tally::score).unwrap_or(0.0);
+                scores.push(score);
+                if score <= 0.0 {
+                    uncovered.push(TechniqueRef {
+                        technique_id: technique.id.clone(),
+                        name: technique.name.clone(),
+                    });
+                } else if score < threshold {
+                    weak.push(Self::technique_coverage(technique, tally));
+                }
+            }
+            if uncovered.is_empty() && weak.is_empty() {
+                continue;
+            }
+            uncovered_total += uncovered.len();
+            weak_total += weak.len();
+            tactics.push(TacticGaps {
+                tactic_id: tactic.id.clone(),
+                name: tactic.name.clone(),
+                score: mean(&scores) * 100.0,
+                uncovered,
+                weak,
+            });
+        }
+
+        GapReport {
+            generated_at: Utc::now(),
+            threshold,
+            uncovered_total,
+            weak_total,
+            tactics,
+            unresolved,
+        }
+    }
+
+    // =========================================================================
+    // Navigator export
+    // =========================================================================
+
+    /// ATT&CK Navigator layer scoring every catalog technique 0-100
+    pub fn navigator_layer(&self, name: &str, description: &str) -> serde_json::Value {
+        let catalog = self.catalog();
+        let (tallies, _) = self.tally(&catalog);
+        let detectors: HashMap<String, Detector> = self.detectors.iter()
+            .map(|d| (d.id.clone(), d.clone()))
+            .collect();
+
+        let entry = |technique: &Technique, show_subtechniques: bool| {
+            let tally = tallies.get(&technique.id);
+            let score = tally.map(Tally::score).unwrap_or(0.0);
+            let names: Vec<serde_json::Value> = tally
+                .map(|t| t.detectors.iter()
+                    .filter_map(|id| detectors.get(id))
+                    .map(|d| serde_json::json!({ "name": "detector", "value": format!("{} ({})", d.name, d.source) }))
+                    .collect())
+                .unwrap_or_default();
+            let comment = match names.len() {
+                0 => "No detection coverage".to_string(),
+                n => format!("{} detector(s)", n),
+            };
+            serde_json::json!({
+                "techniqueID": technique.id,
+                "score": (score * 100.0).round() as u32,
+                "comment": comment,
+                "enabled": true,
+                "metadata": names,
+                "showSubtechniques": show_subtechniques,
+            })
+        };
+
+        let mut techniques = Vec::new();
+        for technique in catalog.techniques() {
+            let subs: Vec<&Technique> = catalog.sub_techniques(&technique.id)
+                .filter(|s| tallies.contains_key(&s.id))
+                .collect();
+            techniques.push(entry(technique, !subs.is_empty()));
+            techniques.extend(subs.into_iter().map(|s| entry(s, false)));
+        }
+
+        let mut versions = serde_json::json!({ "layer": LAYER_VERSION, "navigator": NAVIGATOR_VERSION });
+        if let Some(version) = catalog.version() {
+            versions["attack"] = serde_json::json!(version);
+        }
+
+        serde_json::json!({
+            "name": name,
+            "versions": versions,
+            "domain": "enterprise-attack",
+            "description": description,
+            "sorting": 3,
+            "layout": { "layout": "side", "showID": true, "showName": true },
+            "hideDisabled": false,
+            "techniques": techniques,
+            "gradient": {
+                "colors": ["#ff6666", "#ffe766", "#8ec843"],
+                "minValue": 0,
+                "maxValue": 100,
+            },
+            "legendItems": [],
+            "metadata": [
+                { "name": "generated", "value": Utc::now().to_rfc3339() },
+                { "name": "detectors", "value": detectors.values().filter(|d| d.enabled).count().to_string() },
+            ],
+            "showTacticRowBackground": false,
+            "selectTechniquesAcrossTactics": true,
+        })
+    }
+}
+
+impl Default for MitreRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum MitreError {
+    UnknownTechnique(String),
+    UnknownTactic(String),
+    InvalidDetector(String),
+    InvalidBundle(String),
+}
+
+impl std::fmt::Display for MitreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownTechnique(e) => write!(f, "Unknown ATT&CK technique: {}", e),
+            Self::UnknownTactic(e) => write!(f, "Unknown ATT&CK tactic: {}", e),
+            Self::InvalidDetector(e) => write!(f, "Invalid detector: {}", e),
+            Self::InvalidBundle(e) => write!(f, "Invalid STIX bundle: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MitreError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector(id: &str, techniques: &[&str], confidence: f64) -> Detector {
+        Detector::new(id, id, "test", DetectorKind::Signature)
+            .with_techniques(techniques.iter().copied())
+            .with_confidence(confidence)
+    }
+
+    fn score(report: &CoverageReport, technique: &str) -> f64 {
+        report.techniques.iter()
+            .find(|t| t.technique_id == technique)
+            .map(|t| t.score)
+            .unwrap_or(0.0)
+    }
+
+    /// Credential access with Brute Force and its Credential Stuffing
+    /// sub-technique listed
+    const BUNDLE: &str = r#"{
+        "type": "bundle",
+        "objects": [
+            {"type": "x-mitre-collection", "x_mitre_version": "99.0"},
+            {"type": "x-mitre-tactic", "name": "Credential Access", "x_mitre_shortname": "credential-access",
+             "external_references": [{"source_name": "mitre-attack", "external_id": "TA0006"}]},
+            {"type": "attack-pattern", "name": "Brute Force",
+             "kill_chain_phases": [{"kill_chain_name": "mitre-attack", "phase_name": "credential-access"}],
+             "external_references": [{"source_name": "mitre-attack", "external_id": "T1110"}]},
+            {"type": "attack-pattern", "name": "Credential Stuffing",
+             "kill_chain_phases": [{"kill_chain_name": "mitre-attack", "phase_name": "credential-access"}],
+             "external_references": [{"source_name": "mitre-attack", "external_id": "T1110.004"}]},
+            {"type": "attack-pattern", "name": "Old Technique", "revoked": true,
+             "kill_chain_phases": [{"kill_chain_name": "mitre-attack", "phase_name": "credential-access"}],
+             "external_references": [{"source_name": "mitre-attack", "external_id": "T9999"}]}
+        ]
+    }"#;
+
+    #[test]
+    fn test_register_detector_validation() {
+        let registry = MitreRegistry::new();
+
+        assert!(matches!(
+            registry.register_detector(detector("none", &[], 0.5)),
+            Err(MitreError::InvalidDetector(_))
+        ));
+        assert!(matches!(
+            registry.register_detector(detector("loud", &["T1110"], 1.5)),
+            Err(MitreError::InvalidDetector(_))
+        ));
+        assert!(matches!(
+            registry.register_detector(detector("bogus", &["T0000"], 0.5)),
+            Err(MitreError::UnknownTechnique(t)) if t == "T0000"
+        ));
+
+        // Normalized, and an unlisted sub-technique resolves via its parent
+        registry.register_detector(detector("ids", &[" t1110 ", "T1110", "T1110.004"], 0.5)).unwrap();
+        assert_eq!(registry.detector("ids").unwrap().techniques, vec!["T1110", "T1110.004"]);
+        assert_eq!(registry.detectors_for("T1110").len(), 1);
+        assert!(registry.detectors_for("T1046").is_empty());
+    }
+
+    #[test]
+    fn test_scores_combine_detectors() {
+        let registry = MitreRegistry::new();
+        registry.register_detector(detector("a", &["T1110"], 0.5)).unwrap();
+        registry.register_detector(detector("b", &["T1110"], 0.5)).unwrap();
+        registry.register_detector(detector("off", &["T1046"], 0.9).disabled()).unwrap();
+
+        let report = registry.coverage();
+        assert!((score(&report, "T1110") - 0.75).abs() < 1e-9);
+        assert_eq!(score(&report, "T1046"), 0.0);
+        assert_eq!(report.techniques_covered, 1);
+        assert_eq!(report.detectors, 2);
+
+        let creds = report.tactics.iter().find(|t| t.tactic_id == "TA0006").unwrap();
+        assert_eq!(creds.covered, 1);
+        assert!((creds.score - 75.0 / creds.techniques as f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sub_techniques_credit_parent_at_half() {
+        let registry = MitreRegistry::new();
+        registry.load_catalog(Catalog::from_stix_bundle(BUNDLE).unwrap());
+        registry.register_detector(detector("stuffing", &["T1110.004"], 0.8)).unwrap();
+        // Listing both doesn't count this detector twice towards T1110
+        registry.register_detector(detector("both", &["T1110", "T1110.004"], 0.5)).unwrap();
+
+        let report = registry.coverage();
+        assert_eq!(report.techniques_total, 1);
+        assert!((score(&report, "T1110") - (1.0 - 0.6 * 0.5)).abs() < 1e-9);
+        assert!((score(&report, "T1110.004") - (1.0 - 0.2 * 0.5)).abs() < 1e-9);
+
+        let layer = registry.navigator_layer("Coverage", "test");
+        assert_eq!(layer["versions"]["attack"], "99.0");
+        let techniques = layer["techniques"].as_array().unwrap();
+        assert_eq!(techniques.len(), 2);
+        assert_eq!(techniques[0]["techniqueID"], "T1110");
+        assert_eq!(techniques[0]["score"], 70);
+        assert_eq!(techniques[0]["showSubtechniques"], true);
+        assert_eq!(techniques[1]["techniqueID"], "T1110.004");
+        assert_eq!(techniques[1]["score"], 90);
+    }
+
+    #[test]
+    fn test_gaps_and_unresolved_techniques() {
+        let registry = MitreRegistry::new();
+        registry.register_detector(detector("scan", &["T1046"], 0.4)).unwrap();
+        registry.load_catalog(Catalog::from_stix_bundle(BUNDLE).unwrap());
+        registry.register_detector(detector("weak", &["T1110"], 0.3)).unwrap();
+
+        let gaps = registry.gaps(0.5);
+        assert_eq!(gaps.weak_total, 1);
+        assert_eq!(gaps.uncovered_total, 0);
+        assert_eq!(gaps.tactics[0].weak[0].technique_id, "T1110");
+        assert_eq!(gaps.unresolved.len(), 1);
+        assert_eq!(gaps.unresolved[0].detector_id, "scan");
+
+        registry.unregister_detector("weak");
+        let gaps = registry.gaps(0.5);
+        assert_eq!(gaps.uncovered_total, 1);
+        assert_eq!(gaps.tactics[0].uncovered[0].technique_id, "T1110");
+    }
+
+    #[test]
+    fn test_sync_correlation_rules() {
+        let registry = MitreRegistry::new();
+        let correlator = EventCorrelator::new();
+        registry.register_detector(detector("ids", &["T1110"], 0.5)).unwrap();
+
+        assert_eq!(registry.sync_correlation_rules(&correlator), 4);
+        let sequence = registry.detector("correlation:brute-force-success").unwrap();
+        assert_eq!(sequence.confidence, 0.8);
+        assert_eq!(sequence.kind, DetectorKind::CorrelationRule);
+        assert_eq!(registry.detector("correlation:brute-force").unwrap().confidence, 0.6);
+
+        // 0.5, 0.6 and 0.8 detectors on Brute Force
+        let expected = 1.0 - 0.5 * 0.4 * 0.2;
+        assert!((score(&registry.coverage(), "T1110") - expected).abs() < 1e-9);
+
+        correlator.remove_rule("port-scan");
+        assert_eq!(registry.sync_correlation_rules(&correlator), 3);
+        assert!(registry.detector("correlation:port-scan").is_none());
+        // Detectors from other sources are left alone
+        assert!(registry.detector("ids").is_some());
+    }
+
+    #[test]
+    fn test_event_mappings() {
+        let registry = MitreRegistry::new();
+        assert_eq!(
+            registry.map_event_type(&EventType::BotActivity),
+            (vec!["TA0006".to_string()], vec!["T1110.004".to_string()])
+        );
+
+        let mapping = |tactic: &str, technique: &str| TechniqueMapping {
+            tactics: vec![tactic.to_string()],
+            techniques: vec![technique.to_string()],
+        };
+        assert!(matches!(
+            registry.set_event_mapping(EventType::PortScan, mapping("TA9999", "T1046")),
+            Err(MitreError::UnknownTactic(_))
+        ));
+        assert!(matches!(
+            registry.set_event_mapping(EventType::PortScan, mapping("discovery", "T0000")),
+            Err(MitreError::UnknownTechnique(_))
+        ));
+        registry.set_event_mapping(EventType::PortScan, mapping("discovery", "T1595")).unwrap();
+        assert_eq!(registry.map_event_type(&EventType::PortScan).1, vec!["T1595"]);
+
+        registry.remove_event_mapping(&EventType::PortScan);
+        assert_eq!(registry.map_event_type(&EventType::PortScan), (vec![], vec![]));
+    }
+}
@@ -0,0 +1,193 @@
+//! ArcSight Common Event Format
+//!
+//! `CEF:Version|Vendor|Product|Version|Signature ID|Name|Severity|Extension`
+//!
+//! Header fields escape `|` and `\`; extension values escape `=`, `\` and
+//! line breaks, and may contain unescaped spaces, so a value runs until the
+//! next ` key=`.
+
+use super::{classify, event_time, extract_indicators, severity_from_scale, ParseError};
+use super::CefEvent;
+use crate::{EventSource, SecurityEvent, Severity};
+use std::collections::HashMap;
+
+/// Extension keys mapped to indicators
+const INDICATOR_KEYS: &[&str] = &[
+    "src", "dst", "shost", "dhost", "request", "fileHash", "oldFileHash",
+    "fname", "filePath", "suser", "duser", "sproc", "dproc",
+];
+
+impl CefEvent {
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let body = line.trim()
+            .strip_prefix("CEF:")
+            .ok_or_else(|| ParseError::InvalidFormat("Not CEF format".to_string()))?;
+
+        let (header, extension) = split_header(body, 7);
+        if header.len() < 7 {
+            return Err(ParseError::InvalidFormat(format!(
+                "CEF header has {} of 7 fields", header.len())));
+        }
+        let version = header[0].trim().parse::<u8>()
+            .map_err(|_| ParseError::InvalidFormat(format!("Bad CEF version '{}'", header[0])))?;
+
+        Ok(Self {
+            version,
+            device_vendor: header[1].clone(),
+            device_product: header[2].clone(),
+            device_version: header[3].clone(),
+            signature_id: header[4].clone(),
+            name: header[5].clone(),
+            severity: parse_severity(&header[6]),
+            extensions: parse_extension(extension),
+        })
+    }
+
+    /// Map onto a [`SecurityEvent`]; `raw` is kept in `raw_data`
+    pub fn into_event(self, raw: &str) -> SecurityEvent {
+        let ext = &self.extensions;
+        let category = ext.get("cat").map(String::as_str).unwrap_or_default();
+        let outcome = ext.get("act").or_else(|| ext.get("outcome")).map(String::as_str).unwrap_or_default();
+        let event_type = classify(&[&self.name, &self.signature_id, category, outcome]);
+
+        let severity = match self.severity {
+            // Unknown severity; fall back to the device's own label if any
+            u8::MAX => ext.get("deviceSeverity")
+                .and_then(|s| s.parse::<u8>().ok())
+                .map(severity_from_scale)
+                .unwrap_or(Severity::Info),
+            level => severity_from_scale(level),
+        };
+        let timestamp = ext.get("rt")
+            .or_else(|| ext.get("end"))
+            .or_else(|| ext.get("start"))
+            .and_then(|t| event_time(t));
+        let indicators = extract_indicators(
+            INDICATOR_KEYS.iter().filter_map(|k| ext.get(*k).map(|v| (*k, v.as_str()))),
+        );
+        let description = ext.get("msg")
+            .filter(|m| !m.is_empty())
+            .map(|m| format!("{}: {}", self.name, m))
+            .unwrap_or_else(|| self.name.clone());
+
+        SecurityEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            severity,
+            source: EventSource {
+                system: self.device_vendor.clone(),
+                component: self.device_product.clone(),
+                host: ext.get("dvchost").or_else(|| ext.get("dvc")).cloned(),
+                ip: ext.get("src").cloned(),
+            },
+            timestamp: timestamp.unwrap_or_else(chrono::Utc::now),
+            description,
+            raw_data: serde_json::json!({
+                "raw": raw,
+                "format": "cef",
+                "signature_id": self.signature_id,
+                "cef": self,
+            }),
+            indicators,
+            tags: vec!["cef".to_string()],
+            tenant_id: None,
+        }
+    }
+}
+
+/// Split `count` unescaped-`|` separated header fields off the front,
+/// returning them unescaped with the remainder
+pub(crate) fn split_header(body: &str, count: usize) -> (Vec<String>, &str) {
+    let mut fields = Vec::with_capacity(count);
+    let mut current = String::new();
+    let mut chars = body.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('|' | '\\'))) => current.push(escaped),
+                Some((_, other)) => {
+                    current.push('\\');
+                    current.push(other);
+                }
+                None => current.push('\\'),
+            },
+            '|' => {
+                fields.push(std::mem::take(&mut current));
+                if fields.len() == count {
+                    return (fields, &body[i + 1..]);
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    (fields, "")
+}
+
+/// Numeric 0-10 or the named levels; `u8::MAX` when unknown
+fn parse_severity(field: &str) -> u8 {
+    let field = field.trim();
+    if let Ok(level) = field.parse::<u8>() {
+        return level.min(10);
+    }
+    match field.to_ascii_lowercase().as_str() {
+        "low" => 3,
+        "medium" => 6,
+        "high" => 8,
+        "very-high" | "very high" => 10,
+        _ => u8::MAX,
+    }
+}
+
+fn parse_extension(extension: &str) -> HashMap<String, String> {
+    let chars: Vec<(usize, char)> = extension.char_indices().collect();
+    let is_key_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '[' | ']');
+
+    // (key start, '=' position) for every unescaped `key=`
+    let mut keys: Vec<(usize, usize)> = Vec::new();
+    for (n, &(pos, c)) in chars.iter().enumerate() {
+        if c != '=' {
+            continue;
+        }
+        let backslashes = chars[..n].iter().rev().take_while(|(_, c)| *c == '\\').count();
+        if backslashes % 2 == 1 {
+            continue;
+        }
+        let start = chars[..n].iter().rev().take_while(|(_, c)| is_key_char(*c)).count();
+        if start == 0 {
+            continue;
+        }
+        let key_start = n - start;
+        if key_start == 0 || chars[key_start - 1].1 == ' ' {
+            keys.push((chars[key_start].0, pos));
+        }
+    }
+
+    let mut fields = HashMap::new();
+    for (i, &(key_start, eq)) in keys.iter().enumerate() {
+        let end = keys.get(i + 1).map(|(next, _)| *next).unwrap_or(extension.len());
+        let key = &extension[key_start..eq];
+        let value = unescape_value(extension[eq + 1..end].trim_end());
+        fields.insert(key.to_string(), value);
+    }
+    fields
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(escaped) => out.push(escaped),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
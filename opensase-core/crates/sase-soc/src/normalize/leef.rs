@@ -0,0 +1,126 @@
+//! IBM QRadar Log Event Extended Format
+//!
+//! - LEEF 1.0: `LEEF:1.0|Vendor|Product|Version|EventID|k=v<TAB>k=v`
+//! - LEEF 2.0: `LEEF:2.0|Vendor|Product|Version|EventID|Delimiter|k=v...`
+//!
+//! The 2.0 delimiter is a single character or its hex code (`x09`, `0x5E`);
+//! it defaults to tab when left empty.
+
+use super::cef::split_header;
+use super::{classify, event_time, extract_indicators, severity_from_scale, ParseError};
+use crate::{EventSource, SecurityEvent, Severity};
+use std::collections::HashMap;
+
+const INDICATOR_KEYS: &[&str] = &[
+    "src", "dst", "srcPreNAT", "dstPreNAT", "identHostName", "url", "resource",
+    "usrName", "accountName", "fileHash", "fileName", "process",
+];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LeefEvent {
+    pub version: String,
+    pub vendor: String,
+    pub product: String,
+    pub product_version: String,
+    pub event_id: String,
+    pub attributes: HashMap<String, String>,
+}
+
+impl LeefEvent {
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let body = line.trim()
+            .strip_prefix("LEEF:")
+            .ok_or_else(|| ParseError::InvalidFormat("Not LEEF format".to_string()))?;
+
+        let (header, rest) = split_header(body, 5);
+        if header.len() < 5 {
+            return Err(ParseError::InvalidFormat(format!(
+                "LEEF header has {} of 5 fields", header.len())));
+        }
+        let version = header[0].trim().to_string();
+
+        let (delimiter, attributes) = if version.starts_with('2') {
+            let (field, attributes) = rest.split_once('|').unwrap_or((rest, ""));
+            (parse_delimiter(field)?, attributes)
+        } else if version.starts_with('1') {
+            ('\t', rest)
+        } else {
+            return Err(ParseError::InvalidFormat(format!("Unsupported LEEF version '{}'", version)));
+        };
+
+        let attributes = attributes
+            .split(delimiter)
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .filter(|(k, _)| !k.is_empty())
+            .collect();
+
+        Ok(Self {
+            version,
+            vendor: header[1].clone(),
+            product: header[2].clone(),
+            product_version: header[3].clone(),
+            event_id: header[4].clone(),
+            attributes,
+        })
+    }
+
+    /// Map onto a [`SecurityEvent`]; `raw` is kept in `raw_data`
+    pub fn into_event(self, raw: &str) -> SecurityEvent {
+        let attrs = &self.attributes;
+        let category = attrs.get("cat").map(String::as_str).unwrap_or_default();
+        let action = attrs.get("action").map(String::as_str).unwrap_or_default();
+        let event_type = classify(&[&self.event_id, category, action]);
+
+        let severity = attrs.get("sev")
+            .and_then(|s| s.parse::<u8>().ok())
+            .map(severity_from_scale)
+            .unwrap_or(Severity::Info);
+        let timestamp = attrs.get("devTime").and_then(|t| event_time(t));
+        let indicators = extract_indicators(
+            INDICATOR_KEYS.iter().filter_map(|k| attrs.get(*k).map(|v| (*k, v.as_str()))),
+        );
+        let description = attrs.get("msg")
+            .filter(|m| !m.is_empty())
+            .map(|m| format!("{}: {}", self.event_id, m))
+            .unwrap_or_else(|| format!("{} {} event {}", self.vendor, self.product, self.event_id));
+
+        SecurityEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            severity,
+            source: EventSource {
+                system: self.vendor.clone(),
+                component: self.product.clone(),
+                host: attrs.get("identHostName").or_else(|| attrs.get("devName")).cloned(),
+                ip: attrs.get("src").cloned(),
+            },
+            timestamp: timestamp.unwrap_or_else(chrono::Utc::now),
+            description,
+            raw_data: serde_json::json!({
+                "raw": raw,
+                "format": "leef",
+                "signature_id": self.event_id,
+                "leef": self,
+            }),
+            indicators,
+            tags: vec!["leef".to_string()],
+            tenant_id: None,
+        }
+    }
+}
+
+fn parse_delimiter(field: &str) -> Result<char, ParseError> {
+    let hex = field.strip_prefix("0x")
+        .or_else(|| field.strip_prefix("0X"))
+        .or_else(|| field.strip_prefix('x'))
+        .or_else(|| field.strip_prefix('X'));
+    match (field.chars().count(), hex) {
+        (0, _) => Ok('\t'),
+        (1, _) => Ok(field.chars().next().unwrap_or('\t')),
+        (_, Some(code)) => u32::from_str_radix(code, 16).ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| ParseError::InvalidFormat(format!("Bad LEEF delimiter '{}'", field))),
+        _ => Err(ParseError::InvalidFormat(format!("Bad LEEF delimiter '{}'", field))),
+    }
+}
@@ -0,0 +1,521 @@
+//! Syslog listeners
+//!
+//! UDP (RFC 5426), TCP (RFC 6587) and TLS (RFC 5425) receivers that parse
+//! each message and hand the resulting [`SecurityEvent`] to a channel. On
+//! streams every frame is either octet-counted (`<length> <message>`) or
+//! terminated by a newline; the frame's first bytes decide which.
+//!
+//! Messages that cannot be parsed go to a bounded dead-letter queue, from
+//! which they can be inspected or replayed after fixing the parser setup.
+
+use super::EventNormalizer;
+use crate::SecurityEvent;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Raw bytes kept per dead letter
+const MAX_DEAD_LETTER_BYTES: usize = 16 * 1024;
+/// Longest octet count accepted in a frame header
+const MAX_OCTET_DIGITS: u64 = 10;
+/// Sources silent for this long are dropped when the stats table is full
+const SOURCE_IDLE_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport { Udp, Tcp, Tls }
+
+/// Parsing counters for one sending device
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SourceStats {
+    pub source: String,
+    pub received: u64,
+    pub parsed: u64,
+    pub failed: u64,
+    pub bytes: u64,
+    pub cef: u64,
+    pub leef: u64,
+    pub syslog: u64,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl SourceStats {
+    fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            received: 0,
+            parsed: 0,
+            failed: 0,
+            bytes: 0,
+            cef: 0,
+            leef: 0,
+            syslog: 0,
+            last_seen: None,
+            last_error: None,
+        }
+    }
+}
+
+/// Message that could not be parsed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetter {
+    pub received_at: DateTime<Utc>,
+    pub source: String,
+    pub transport: Transport,
+    /// The message, truncated to 16 KiB
+    pub raw: String,
+    pub error: String,
+}
+
+enum Frame {
+    Message(Vec<u8>),
+    /// Oversized message; only its start is kept
+    TooLong(Vec<u8>),
+}
+
+pub struct SyslogListener {
+    normalizer: EventNormalizer,
+    sink: mpsc::Sender<SecurityEvent>,
+    udp: Option<SocketAddr>,
+    tcp: Option<SocketAddr>,
+    tls: Option<(SocketAddr, tokio_rustls::TlsAcceptor)>,
+    /// Tenant assigned to events that don't name one
+    tenant_id: Option<String>,
+    max_message_bytes: usize,
+    idle_timeout: Duration,
+    stats: dashmap::DashMap<String, SourceStats>,
+    max_sources: usize,
+    dead_letters: parking_lot::Mutex<VecDeque<DeadLetter>>,
+    dead_letter_capacity: usize,
+}
+
+impl SyslogListener {
+    /// Listener delivering parsed events to `sink`; add at least one
+    /// transport before starting it
+    pub fn new(sink: mpsc::Sender<SecurityEvent>) -> Self {
+        Self {
+            normalizer: EventNormalizer::new(),
+            sink,
+            udp: None,
+            tcp: None,
+            tls: None,
+            tenant_id: None,
+            max_message_bytes: 64 * 1024,
+            idle_timeout: Duration::from_secs(300),
+            stats: dashmap::DashMap::new(),
+            max_sources: 10_000,
+            dead_letters: parking_lot::Mutex::new(VecDeque::new()),
+            dead_letter_capacity: 10_000,
+        }
+    }
+
+    pub fn with_udp(mut self, addr: SocketAddr) -> Self {
+        self.udp = Some(addr);
+        self
+    }
+
+    pub fn with_tcp(mut self, addr: SocketAddr) -> Self {
+        self.tcp = Some(addr);
+        self
+    }
+
+    pub fn with_tls(mut self, addr: SocketAddr, acceptor: tokio_rustls::TlsAcceptor) -> Self {
+        self.tls = Some((addr, acceptor));
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = Some(tenant_id.to_string());
+        self
+    }
+
+    pub fn with_max_message_bytes(mut self, max: usize) -> Self {
+        self.max_message_bytes = max.max(480);
+        self
+    }
+
+    /// Close stream connections silent for this long
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Sending devices tracked in [`Self::source_stats`]. When full, idle
+    /// sources are dropped first, then the least recently seen ones.
+    pub fn with_max_sources(mut self, max: usize) -> Self {
+        self.max_sources = max.max(1);
+        self
+    }
+
+    pub fn with_dead_letter_capacity(mut self, capacity: usize) -> Self {
+        self.dead_letter_capacity = capacity;
+        self
+    }
+
+    /// Parsers and signature mappings used for incoming messages
+    pub fn normalizer(&self) -> &EventNormalizer {
+        &self.normalizer
+    }
+
+    /// Bind every configured transport and serve them in the background.
+    /// Fails without serving anything if a socket cannot be bound.
+    pub async fn start(self: Arc<Self>) -> std::io::Result<Vec<JoinHandle<()>>> {
+        let udp = match self.udp {
+            Some(addr) => Some(UdpSocket::bind(addr).await?),
+            None => None,
+        };
+        let tcp = match self.tcp {
+            Some(addr) => Some(TcpListener::bind(addr).await?),
+            None => None,
+        };
+        let tls = match &self.tls {
+            Some((addr, acceptor)) => Some((TcpListener::bind(addr).await?, acceptor.clone())),
+            None => None,
+        };
+
+        let mut handles = Vec::new();
+        if let Some(socket) = udp {
+            tracing::info!("Syslog UDP listener on {}", socket.local_addr()?);
+            handles.push(tokio::spawn(self.clone().serve_udp(socket)));
+        }
+        if let Some(listener) = tcp {
+            tracing::info!("Syslog TCP listener on {}", listener.local_addr()?);
+            handles.push(tokio::spawn(self.clone().serve_tcp(listener, None)));
+        }
+        if let Some((listener, acceptor)) = tls {
+            tracing::info!("Syslog TLS listener on {}", listener.local_addr()?);
+            handles.push(tokio::spawn(self.clone().serve_tcp(listener, Some(acceptor))));
+        }
+        Ok(handles)
+    }
+
+    async fn serve_udp(self: Arc<Self>, socket: UdpSocket) {
+        let mut buf = vec![0u8; 65_535];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    tracing::warn!("Syslog UDP receive failed: {}", e);
+                    continue;
+                }
+            };
+            // Some senders batch several messages per datagram
+            for message in buf[..len].split(|b| *b == b'\n') {
+                self.handle(message, peer.ip(), Transport::Udp).await;
+            }
+        }
+    }
+
+    async fn serve_tcp(self: Arc<Self>, listener: TcpListener, tls: Option<tokio_rustls::TlsAcceptor>) {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Syslog accept failed: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+
+            let listener = self.clone();
+            let tls = tls.clone();
+            tokio::spawn(async move {
+                match tls {
+                    None => listener.serve_stream(stream, peer, Transport::Tcp).await,
+                    Some(acceptor) => {
+                        match tokio::time::timeout(listener.idle_timeout, acceptor.accept(stream)).await {
+                            Ok(Ok(tls)) => listener.serve_stream(tls, peer, Transport::Tls).await,
+                            Ok(Err(e)) => tracing::warn!("Syslog TLS handshake from {} failed: {}", peer, e),
+                            Err(_) => tracing::warn!("Syslog TLS handshake from {} timed out", peer),
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    async fn serve_stream<S: AsyncRead + Unpin>(&self, stream: S, peer: SocketAddr, transport: Transport) {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let frame = match tokio::time::timeout(
+                self.idle_timeout,
+                read_frame(&mut reader, self.max_message_bytes),
+            ).await {
+                Ok(Ok(Some(frame))) => frame,
+                Ok(Ok(None)) => break,
+                Ok(Err(e)) => {
+                    tracing::debug!("Syslog connection from {} failed: {}", peer, e);
+                    break;
+                }
+                Err(_) => {
+                    tracing::debug!("Syslog connection from {} idle, closing", peer);
+                    break;
+                }
+            };
+
+            match frame {
+                Frame::Message(message) => self.handle(&message, peer.ip(), transport).await,
+                Frame::TooLong(start) => {
+                    let source = peer.ip().to_string();
+                    let error = format!("message exceeds {} bytes", self.max_message_bytes);
+                    self.count(&source, start.len(), Err(&error));
+                    self.dead_letter(&source, transport, &String::from_utf8_lossy(&start), error);
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, message: &[u8], peer: IpAddr, transport: Transport) {
+        let text = String::from_utf8_lossy(message);
+        let line = text.trim_end_matches(['\r', '\n', '\0']);
+        if line.trim().is_empty() {
+            return;
+        }
+        let source = peer.to_string();
+
+        match self.normalizer.normalize("syslog", line) {
+            Ok(mut event) => {
+                if event.tenant_id.is_none() {
+                    event.tenant_id = self.tenant_id.clone();
+                }
+                if let serde_json::Value::Object(raw) = &mut event.raw_data {
+                    raw.insert("received_from".to_string(), serde_json::json!(source));
+                    raw.insert("transport".to_string(), serde_json::json!(transport));
+                }
+                let format = event.raw_data.get("format").and_then(|f| f.as_str()).unwrap_or("syslog");
+                self.count(&source, message.len(), Ok(format));
+
+                if self.sink.send(event).await.is_err() {
+                    tracing::warn!("Syslog event sink closed, dropping message from {}", source);
+                }
+            }
+            Err(e) => {
+                let error = e.to_string();
+                self.count(&source, message.len(), Err(&error));
+                self.dead_letter(&source, transport, line, error);
+            }
+        }
+    }
+
+    fn count(&self, source: &str, bytes: usize, outcome: Result<&str, &str>) {
+        // UDP sources are unauthenticated, so spoofed addresses must not grow the table without bound
+        if self.stats.len() >= self.max_sources && !self.stats.contains_key(source) {
+            self.evict_sources();
+        }
+        let mut stats = self.stats.entry(source.to_string()).or_insert_with(|| SourceStats::new(source));
+        stats.received += 1;
+        stats.bytes += bytes as u64;
+        stats.last_seen = Some(Utc::now());
+        match outcome {
+            Ok(format) => {
+                stats.parsed += 1;
+                match format {
+                    "cef" => stats.cef += 1,
+                    "leef" => stats.leef += 1,
+                    _ => stats.syslog += 1,
+                }
+            }
+            Err(error) => {
+                stats.failed += 1;
+                stats.last_error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// Make room in the stats table: drop idle sources, then the least
+    /// recently seen tenth so eviction isn't repeated for every new source
+    fn evict_sources(&self) {
+        let idle_cutoff = Utc::now() - chrono::Duration::hours(SOURCE_IDLE_HOURS);
+        self.stats.retain(|_, s| s.last_seen.is_some_and(|seen| seen >= idle_cutoff));
+
+        let keep = (self.max_sources - self.max_sources / 10).saturating_sub(1);
+        let excess = self.stats.len().saturating_sub(keep);
+        if excess == 0 {
+            return;
+        }
+        let mut seen: Vec<(Option<DateTime<Utc>>, String)> = self.stats.iter()
+            .map(|s| (s.last_seen, s.source.clone()))
+            .collect();
+        seen.sort_unstable();
+        for (_, source) in seen.into_iter().take(excess) {
+            self.stats.remove(&source);
+        }
+    }
+
+    fn dead_letter(&self, source: &str, transport: Transport, raw: &str, error: String) {
+        tracing::debug!("Unparseable syslog message from {}: {}", source, error);
+        if self.dead_letter_capacity == 0 {
+            return;
+        }
+
+        let mut end = raw.len().min(MAX_DEAD_LETTER_BYTES);
+        while !raw.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut queue = self.dead_letters.lock();
+        if queue.len() >= self.dead_letter_capacity {
+            queue.pop_front();
+        }
+        queue.push_back(DeadLetter {
+            received_at: Utc::now(),
+            source: source.to_string(),
+            transport,
+            raw: raw[..end].to_string(),
+            error,
+        });
+    }
+
+    /// Parsing counters per sending device
+    pub fn source_stats(&self) -> Vec<SourceStats> {
+        let mut stats: Vec<SourceStats> = self.stats.iter().map(|s| s.clone()).collect();
+        stats.sort_by(|a, b| a.source.cmp(&b.source));
+        stats
+    }
+
+    /// Queued dead letters, oldest first
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().iter().cloned().collect()
+    }
+
+    /// Remove and return all dead letters
+    pub fn drain_dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().drain(..).collect()
+    }
+
+    /// Parse queued dead letters again, e.g. after registering a parser or
+    /// fixing a device's format. Returns how many were delivered; the rest
+    /// stay queued.
+    pub async fn replay_dead_letters(&self) -> usize {
+        let letters = self.drain_dead_letters();
+        let mut delivered = 0;
+        let mut failed = Vec::new();
+
+        for mut letter in letters {
+            match self.normalizer.normalize("syslog", &letter.raw) {
+                Ok(mut event) => {
+                    if event.tenant_id.is_none() {
+                        event.tenant_id = self.tenant_id.clone();
+                    }
+                    if self.sink.send(event).await.is_err() {
+                        tracing::warn!("Syslog event sink closed during replay");
+                        failed.push(letter);
+                        continue;
+                    }
+                    delivered += 1;
+                }
+                Err(e) => {
+                    letter.error = e.to_string();
+                    failed.push(letter);
+                }
+            }
+        }
+
+        // Requeue ahead of anything that arrived during the replay
+        let mut queue = self.dead_letters.lock();
+        for letter in failed.into_iter().rev() {
+            queue.push_front(letter);
+        }
+        while queue.len() > self.dead_letter_capacity {
+            queue.pop_front();
+        }
+        delivered
+    }
+}
+
+/// Next frame on a stream, `None` at end of stream
+async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R, max: usize) -> std::io::Result<Option<Frame>> {
+    let first = match reader.fill_buf().await?.first() {
+        Some(b) => *b,
+        None => return Ok(None),
+    };
+
+    let mut start = Vec::new();
+    if first.is_ascii_digit() {
+        // Octet counting: `<digits> <message>`
+        (&mut *reader).take(MAX_OCTET_DIGITS + 1).read_until(b' ', &mut start).await?;
+        let length = std::str::from_utf8(&start).ok()
+            .and_then(|s| s.strip_suffix(' '))
+            .and_then(|digits| digits.parse::<usize>().ok());
+
+        if let Some(length) = length {
+            if length > max {
+                let mut kept = Vec::new();
+                (&mut *reader).take(max as u64).read_to_end(&mut kept).await?;
+                let rest = (length - kept.len()) as u64;
+                tokio::io::copy(&mut (&mut *reader).take(rest), &mut tokio::io::sink()).await?;
+                return Ok(Some(Frame::TooLong(kept)));
+            }
+            let mut message = vec![0u8; length];
+            reader.read_exact(&mut message).await?;
+            return Ok(Some(Frame::Message(message)));
+        }
+        // Not a length after all; the digits start a newline-framed message
+    }
+
+    let mut overflow = false;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            break;
+        }
+        let (take, done) = match available.iter().position(|b| *b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (available.len(), false),
+        };
+        let room = (max + 1).saturating_sub(start.len());
+        start.extend_from_slice(&available[..take.min(room)]);
+        overflow |= take > room;
+        reader.consume(take);
+        if done {
+            break;
+        }
+    }
+
+    if overflow || start.len() > max {
+        start.truncate(max);
+        return Ok(Some(Frame::TooLong(start)));
+    }
+    Ok(Some(Frame::Message(start)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_stats_bounded() {
+        let (tx, _rx) = mpsc::channel(1);
+        let listener = SyslogListener::new(tx).with_max_sources(10);
+
+        listener.count("10.0.0.1", 100, Ok("cef"));
+        listener.stats.get_mut("10.0.0.1").unwrap().last_seen = Some(Utc::now() - chrono::Duration::hours(48));
+        for i in 2..=10 {
+            listener.count(&format!("10.0.0.{}", i), 100, Ok("syslog"));
+        }
+        assert_eq!(listener.source_stats().len(), 10);
+
+        // Known sources never trigger eviction
+        listener.count("10.0.0.5", 100, Err("bad header"));
+        assert_eq!(listener.source_stats().len(), 10);
+
+        // A new source drops the idle one first, then the least recently seen
+        listener.count("10.0.0.11", 100, Ok("leef"));
+        let sources: Vec<String> = listener.source_stats().into_iter().map(|s| s.source).collect();
+        assert!(sources.len() <= 10);
+        assert!(!sources.contains(&"10.0.0.1".to_string()));
+        assert!(sources.contains(&"10.0.0.5".to_string()));
+        assert!(sources.contains(&"10.0.0.11".to_string()));
+
+        for i in 0..1000 {
+            listener.count(&format!("192.0.2.{}", i), 1, Ok("syslog"));
+        }
+        assert!(listener.source_stats().len() <= 10);
+    }
+}
@@ -0,0 +1,442 @@
+//! Event Normalization
+//!
+//! Convert raw logs to Common Event Format (CEF).
+//!
+//! Third-party events arrive as CEF, LEEF or plain syslog, usually over the
+//! [`SyslogListener`]. Parsers map them onto [`SecurityEvent`], deriving the
+//! event type from the vendor's name and category fields and the severity
+//! from the vendor's 0-10 scale. Signatures the keyword mapping gets wrong
+//! can be pinned with [`EventNormalizer::map_signature`].
+
+mod cef;
+mod leef;
+mod listener;
+mod syslog;
+
+pub use leef::LeefEvent;
+pub use listener::{DeadLetter, SourceStats, SyslogListener, Transport};
+pub use syslog::SyslogMessage;
+
+use crate::{SecurityEvent, EventType, Severity, EventSource, Indicator, IndicatorType};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+
+pub struct EventNormalizer {
+    parsers: dashmap::DashMap<String, Box<dyn EventParser>>,
+    /// Event type per `vendor|signature id`, overriding classification
+    signatures: dashmap::DashMap<String, EventType>,
+    stats: NormalizerStats,
+}
+
+struct NormalizerStats {
+    events_processed: std::sync::atomic::AtomicU64,
+    events_failed: std::sync::atomic::AtomicU64,
+}
+
+#[async_trait::async_trait]
+pub trait EventParser: Send + Sync {
+    fn source_type(&self) -> &str;
+    fn parse(&self, raw: &str) -> Result<SecurityEvent, ParseError>;
+}
+
+/// Common Event Format (CEF) event
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct CefEvent {
+    pub version: u8,
+    pub device_vendor: String,
+    pub device_product: String,
+    pub device_version: String,
+    pub signature_id: String,
+    pub name: String,
+    pub severity: u8,
+    pub extensions: HashMap<String, String>,
+}
+
+impl EventNormalizer {
+    pub fn new() -> Self {
+        let normalizer = Self {
+            parsers: dashmap::DashMap::new(),
+            signatures: dashmap::DashMap::new(),
+            stats: NormalizerStats {
+                events_processed: std::sync::atomic::AtomicU64::new(0),
+                events_failed: std::sync::atomic::AtomicU64::new(0),
+            },
+        };
+        normalizer.register_default_parsers();
+        normalizer
+    }
+    
+    fn register_default_parsers(&self) {
+        self.parsers.insert("syslog".to_string(), Box::new(SyslogParser));
+        self.parsers.insert("json".to_string(), Box::new(JsonParser));
+        self.parsers.insert("cef".to_string(), Box::new(CefParser));
+        self.parsers.insert("leef".to_string(), Box::new(LeefParser));
+    }
+    
+    pub fn normalize(&self, source_type: &str, raw: &str) -> Result<SecurityEvent, ParseError> {
+        let parser = self.parsers.get(source_type)
+            .ok_or(ParseError::UnknownSource(source_type.to_string()))?;
+        
+        match parser.parse(raw) {
+            Ok(mut event) => {
+                self.stats.events_processed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if let Some(signature) = event.raw_data.get("signature_id").and_then(|s| s.as_str()) {
+                    let key = signature_key(&event.source.system, signature);
+                    if let Some(event_type) = self.signatures.get(&key) {
+                        event.event_type = *event_type;
+                    }
+                }
+                Ok(event)
+            }
+            Err(e) => {
+                self.stats.events_failed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+    
+    pub fn to_cef(&self, event: &SecurityEvent) -> CefEvent {
+        let mut extensions = HashMap::new();
+        extensions.insert("src".to_string(), event.source.ip.clone().unwrap_or_default());
+        extensions.insert("shost".to_string(), event.source.host.clone().unwrap_or_default());
+        extensions.insert("rt".to_string(), event.timestamp.timestamp_millis().to_string());
+        extensions.insert("msg".to_string(), event.description.clone());
+        
+        for (i, indicator) in event.indicators.iter().enumerate() {
+            extensions.insert(format!("cs{}Label", i+1), format!("{:?}", indicator.indicator_type));
+            extensions.insert(format!("cs{}", i+1), indicator.value.clone());
+        }
+        
+        CefEvent {
+            version: 0,
+            device_vendor: "OpenSASE".to_string(),
+            device_product: event.source.component.clone(),
+            device_version: "1.0".to_string(),
+            signature_id: format!("{:?}", event.event_type),
+            name: event.description.clone(),
+            severity: match event.severity {
+                Severity::Info => 0,
+                Severity::Low => 3,
+                Severity::Medium => 5,
+                Severity::High => 8,
+                Severity::Critical => 10,
+            },
+            extensions,
+        }
+    }
+    
+    pub fn register_parser(&self, parser: Box<dyn EventParser>) {
+        self.parsers.insert(parser.source_type().to_string(), parser);
+    }
+    
+    /// Pin the event type of one vendor signature (CEF signature ID or
+    /// LEEF event ID)
+    pub fn map_signature(&self, vendor: &str, signature_id: &str, event_type: EventType) {
+        self.signatures.insert(signature_key(vendor, signature_id), event_type);
+    }
+}
+
+fn signature_key(vendor: &str, signature_id: &str) -> String {
+    format!("{}|{}", vendor.to_ascii_lowercase(), signature_id)
+}
+
+impl Default for EventNormalizer {
+    fn default() -> Self { Self::new() }
+}
+
+// Syslog Parser
+struct SyslogParser;
+
+#[async_trait::async_trait]
+impl EventParser for SyslogParser {
+    fn source_type(&self) -> &str { "syslog" }
+    
+    fn parse(&self, raw: &str) -> Result<SecurityEvent, ParseError> {
+        // Bare CEF/LEEF lines without a syslog header
+        let line = raw.trim();
+        if line.starts_with("CEF:") {
+            return CefParser.parse(line);
+        }
+        if line.starts_with("LEEF:") {
+            return LeefParser.parse(line);
+        }
+        
+        let header = SyslogMessage::parse(raw)?;
+        let body = header.message.as_str();
+        let payload = ["CEF:", "LEEF:"].iter()
+            .find_map(|tag| {
+                if body.starts_with(tag) {
+                    Some(0)
+                } else {
+                    body.find(&format!(" {}", tag)).map(|i| i + 1)
+                }
+            })
+            .map(|at| &body[at..]);
+        
+        let (mut event, dated) = match payload {
+            Some(payload) if payload.starts_with("CEF:") => {
+                let cef = CefEvent::parse(payload)?;
+                let dated = ["rt", "end", "start"].iter().any(|k| cef.extensions.contains_key(*k));
+                (cef.into_event(raw), dated)
+            }
+            Some(payload) => {
+                let leef = LeefEvent::parse(payload)?;
+                let dated = leef.attributes.contains_key("devTime");
+                (leef.into_event(raw), dated)
+            }
+            None => return Ok(header.into_event(raw)),
+        };
+        
+        // Fill gaps in the payload from the syslog header
+        if event.source.host.is_none() {
+            event.source.host = header.hostname.clone();
+        }
+        if !dated {
+            if let Some(timestamp) = header.timestamp {
+                event.timestamp = timestamp;
+            }
+        }
+        event.tags.push("syslog".to_string());
+        Ok(event)
+    }
+}
+
+// JSON Parser
+struct JsonParser;
+
+#[async_trait::async_trait]
+impl EventParser for JsonParser {
+    fn source_type(&self) -> &str { "json" }
+    
+    fn parse(&self, raw: &str) -> Result<SecurityEvent, ParseError> {
+        let value: serde_json::Value = serde_json::from_str(raw)
+            .map_err(|e| ParseError::InvalidFormat(e.to_string()))?;
+        
+        Ok(SecurityEvent {
+            id: value.get("id").and_then(|v| v.as_str()).unwrap_or(&uuid::Uuid::new_v4().to_string()).to_string(),
+            event_type: EventType::Custom,
+            severity: Severity::Info,
+            source: EventSource {
+                system: value.get("source").and_then(|v| v.as_str()).unwrap_or("json").to_string(),
+                component: value.get("component").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+                host: value.get("host").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                ip: value.get("ip").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            },
+            timestamp: chrono::Utc::now(),
+            description: value.get("message").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+            raw_data: value,
+            indicators: vec![],
+            tags: vec!["json".to_string()],
+            tenant_id: None,
+        })
+    }
+}
+
+// CEF Parser
+struct CefParser;
+
+#[async_trait::async_trait]
+impl EventParser for CefParser {
+    fn source_type(&self) -> &str { "cef" }
+    
+    fn parse(&self, raw: &str) -> Result<SecurityEvent, ParseError> {
+        Ok(CefEvent::parse(raw)?.into_event(raw))
+    }
+}
+
+// LEEF Parser
+struct LeefParser;
+
+#[async_trait::async_trait]
+impl EventParser for LeefParser {
+    fn source_type(&self) -> &str { "leef" }
+    
+    fn parse(&self, raw: &str) -> Result<SecurityEvent, ParseError> {
+        Ok(LeefEvent::parse(raw)?.into_event(raw))
+    }
+}
+
+// =============================================================================
+// Field Mapping
+// =============================================================================
+
+/// Event types by keyword, most specific first. A trailing `*` matches
+/// any word starting with the keyword.
+const EVENT_KEYWORDS: &[(&str, EventType)] = &[
+    ("impossible travel", EventType::ImpossibleTravel),
+    ("account compromise*", EventType::AccountCompromise),
+    ("compromised account*", EventType::AccountCompromise),
+    ("brute*", EventType::BruteForceAttempt),
+    ("password spray*", EventType::BruteForceAttempt),
+    ("login fail*", EventType::AuthenticationFailure),
+    ("logon fail*", EventType::AuthenticationFailure),
+    ("authentication fail*", EventType::AuthenticationFailure),
+    ("auth fail*", EventType::AuthenticationFailure),
+    ("failed login*", EventType::AuthenticationFailure),
+    ("failed logon*", EventType::AuthenticationFailure),
+    ("failed password*", EventType::AuthenticationFailure),
+    ("invalid password*", EventType::AuthenticationFailure),
+    ("privilege escalation", EventType::PrivilegeEscalation),
+    ("privesc", EventType::PrivilegeEscalation),
+    ("ransomware", EventType::MalwareDetected),
+    ("malware", EventType::MalwareDetected),
+    ("virus*", EventType::MalwareDetected),
+    ("trojan*", EventType::MalwareDetected),
+    ("worm*", EventType::MalwareDetected),
+    ("backdoor*", EventType::MalwareDetected),
+    ("spyware", EventType::MalwareDetected),
+    ("suspicious process*", EventType::SuspiciousProcess),
+    ("process injection", EventType::SuspiciousProcess),
+    ("file integrity", EventType::FileIntegrity),
+    ("exfiltration", EventType::DataExfiltration),
+    ("exfil", EventType::DataExfiltration),
+    ("dlp", EventType::DlpViolation),
+    ("data loss", EventType::DlpViolation),
+    ("ddos", EventType::DdosAttack),
+    ("dos attack", EventType::DdosAttack),
+    ("flood*", EventType::DdosAttack),
+    ("port scan*", EventType::PortScan),
+    ("portscan*", EventType::PortScan),
+    ("host sweep*", EventType::PortScan),
+    ("sql injection", EventType::WebAttack),
+    ("sqli", EventType::WebAttack),
+    ("xss", EventType::WebAttack),
+    ("cross site scripting", EventType::WebAttack),
+    ("web attack*", EventType::WebAttack),
+    ("directory traversal", EventType::WebAttack),
+    ("api abuse", EventType::ApiAbuse),
+    ("bot", EventType::BotActivity),
+    ("botnet", EventType::BotActivity),
+    ("intrusion", EventType::NetworkIntrusion),
+    ("exploit*", EventType::NetworkIntrusion),
+    ("ips", EventType::NetworkIntrusion),
+    ("ids", EventType::NetworkIntrusion),
+    ("unauthorized", EventType::UnauthorizedAccess),
+    ("access denied", EventType::UnauthorizedAccess),
+    ("compliance", EventType::ComplianceViolation),
+    ("policy violation*", EventType::PolicyViolation),
+    ("deny", EventType::SuspiciousTraffic),
+    ("denied", EventType::SuspiciousTraffic),
+    ("drop", EventType::SuspiciousTraffic),
+    ("dropped", EventType::SuspiciousTraffic),
+    ("blocked", EventType::SuspiciousTraffic),
+    ("scan", EventType::PortScan),
+];
+
+/// Event type from vendor text fields (name, category, action, ...)
+pub(crate) fn classify(texts: &[&str]) -> EventType {
+    let words: Vec<String> = texts.iter()
+        .flat_map(|t| t.split(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+
+    let matches = |keyword: &str| {
+        let (keyword, prefix) = match keyword.strip_suffix('*') {
+            Some(k) => (k, true),
+            None => (keyword, false),
+        };
+        let parts: Vec<&str> = keyword.split(' ').collect();
+        words.windows(parts.len()).any(|window| {
+            window.iter().zip(&parts).enumerate().all(|(i, (word, part))| {
+                if prefix && i == parts.len() - 1 { word.starts_with(*part) } else { word.as_str() == *part }
+            })
+        })
+    };
+
+    EVENT_KEYWORDS.iter()
+        .find(|(keyword, _)| matches(keyword))
+        .map(|(_, event_type)| *event_type)
+        .unwrap_or(EventType::Custom)
+}
+
+/// CEF/LEEF 0-10 scale: 0 unknown, 1-3 low, 4-6 medium, 7-8 high,
+/// 9-10 very high
+pub(crate) fn severity_from_scale(level: u8) -> Severity {
+    match level {
+        0 => Severity::Info,
+        1..=3 => Severity::Low,
+        4..=6 => Severity::Medium,
+        7..=8 => Severity::High,
+        _ => Severity::Critical,
+    }
+}
+
+/// Epoch seconds or milliseconds, RFC 3339, or the common
+/// `MMM dd yyyy HH:mm:ss` device formats
+pub(crate) fn event_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        let n: i64 = value.parse().ok()?;
+        return if value.len() >= 13 {
+            Utc.timestamp_millis_opt(n).single()
+        } else {
+            Utc.timestamp_opt(n, 0).single()
+        };
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Some(t.with_timezone(&Utc));
+    }
+    for format in ["%b %d %Y %H:%M:%S%.f %z", "%d/%b/%Y:%H:%M:%S %z"] {
+        if let Ok(t) = DateTime::parse_from_str(value, format) {
+            return Some(t.with_timezone(&Utc));
+        }
+    }
+    ["%b %d %Y %H:%M:%S%.f", "%b %d %Y %H:%M:%S", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|t| Utc.from_utc_datetime(&t))
+}
+
+/// Indicators from `(field, value)` pairs, typed by field name and value
+pub(crate) fn extract_indicators<'a>(fields: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<Indicator> {
+    let mut indicators: Vec<Indicator> = Vec::new();
+    for (field, value) in fields {
+        let value = value.trim();
+        if value.is_empty() || value == "-" || indicators.iter().any(|i| i.value == value) {
+            continue;
+        }
+        let key = field.to_ascii_lowercase();
+        let indicator_type = if value.parse::<std::net::IpAddr>().is_ok() {
+            IndicatorType::IpAddress
+        } else if key.contains("hash") {
+            IndicatorType::Hash
+        } else if value.contains("://") {
+            IndicatorType::Url
+        } else if key.contains("user") || key.contains("account") {
+            if value.contains('@') { IndicatorType::Email } else { IndicatorType::Username }
+        } else if key.contains("proc") {
+            IndicatorType::Process
+        } else if key.starts_with("file") || key == "fname" {
+            IndicatorType::FileName
+        } else if key.contains("host") && value.contains('.') {
+            IndicatorType::Domain
+        } else {
+            continue;
+        };
+        indicators.push(Indicator {
+            indicator_type,
+            value: value.to_string(),
+            confidence: 1.0,
+            context: Some(field.to_string()),
+        });
+    }
+    indicators
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    UnknownSource(String),
+    InvalidFormat(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownSource(s) => write!(f, "Unknown source: {}", s),
+            Self::InvalidFormat(s) => write!(f, "Invalid format: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,210 @@
+//! Syslog message framing
+//!
+//! Parses RFC 5424 and BSD (RFC 3164) headers. The `<PRI>` part is optional
+//! since many relays strip it. The message body is returned untouched so a
+//! CEF or LEEF payload can be parsed from it.
+
+use super::{classify, extract_indicators, ParseError};
+use crate::{EventSource, SecurityEvent, Severity};
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone, Utc};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SyslogMessage {
+    pub facility: Option<u8>,
+    /// 0 (emergency) to 7 (debug)
+    pub severity: Option<u8>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    pub message: String,
+}
+
+impl SyslogMessage {
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let line = line.trim_end_matches(['\r', '\n', '\0']);
+        if line.trim().is_empty() {
+            return Err(ParseError::InvalidFormat("Empty syslog message".to_string()));
+        }
+
+        let (pri, rest) = match line.strip_prefix('<') {
+            Some(after) => {
+                let (digits, rest) = after.split_once('>')
+                    .ok_or_else(|| ParseError::InvalidFormat("Unterminated syslog PRI".to_string()))?;
+                let pri = digits.parse::<u8>().ok().filter(|p| *p <= 191)
+                    .ok_or_else(|| ParseError::InvalidFormat(format!("Bad syslog PRI '{}'", digits)))?;
+                (Some(pri), rest)
+            }
+            None => (None, line),
+        };
+
+        let mut message = match rest.strip_prefix("1 ") {
+            Some(body) => parse_rfc5424(body),
+            None => parse_rfc3164(rest),
+        };
+        message.facility = pri.map(|p| p / 8);
+        message.severity = pri.map(|p| p % 8);
+        Ok(message)
+    }
+
+    /// Event for a plain syslog line without a CEF or LEEF payload
+    pub fn into_event(self, raw: &str) -> SecurityEvent {
+        let severity = match self.severity {
+            Some(0..=1) => Severity::Critical,
+            Some(2) => Severity::High,
+            Some(3) => Severity::Medium,
+            Some(4) => Severity::Low,
+            _ => Severity::Info,
+        };
+        let words = self.message.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != ':'))
+            .filter(|w| w.parse::<std::net::IpAddr>().is_ok())
+            .map(|w| ("ip", w));
+        let indicators = extract_indicators(words);
+
+        SecurityEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type: classify(&[&self.message]),
+            severity,
+            source: EventSource {
+                system: "syslog".to_string(),
+                component: self.app_name.clone().unwrap_or_else(|| "unknown".to_string()),
+                host: self.hostname.clone(),
+                ip: None,
+            },
+            timestamp: self.timestamp.unwrap_or_else(Utc::now),
+            description: self.message.clone(),
+            raw_data: serde_json::json!({
+                "raw": raw,
+                "format": "syslog",
+                "syslog": self,
+            }),
+            indicators,
+            tags: vec!["syslog".to_string()],
+            tenant_id: None,
+        }
+    }
+}
+
+/// `TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD] MSG`, `-` for nil
+fn parse_rfc5424(body: &str) -> SyslogMessage {
+    let mut rest = body;
+    let mut next = || {
+        let (token, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        rest = tail;
+        (token != "-" && !token.is_empty()).then(|| token.to_string())
+    };
+    let timestamp = next().and_then(|t| DateTime::parse_from_rfc3339(&t).ok()).map(|t| t.with_timezone(&Utc));
+    let hostname = next();
+    let app_name = next();
+    let proc_id = next();
+    let msg_id = next();
+
+    SyslogMessage {
+        facility: None,
+        severity: None,
+        timestamp,
+        hostname,
+        app_name,
+        proc_id,
+        msg_id,
+        message: skip_structured_data(rest).trim_start_matches('\u{feff}').to_string(),
+    }
+}
+
+fn skip_structured_data(rest: &str) -> &str {
+    if let Some(tail) = rest.strip_prefix("- ") {
+        return tail;
+    }
+    if rest == "-" || !rest.starts_with('[') {
+        return if rest == "-" { "" } else { rest };
+    }
+
+    // Skip `[...]` elements; `\]` inside values is escaped
+    let mut escaped = false;
+    let mut depth = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' if depth == 0 => depth = 1,
+            ']' if depth == 1 => {
+                depth = 0;
+                if !rest[i + 1..].starts_with('[') {
+                    return rest[i + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// `Mmm dd hh:mm:ss HOSTNAME TAG[PID]: MSG`; devices often send an ISO
+/// timestamp instead
+fn parse_rfc3164(rest: &str) -> SyslogMessage {
+    let mut message = SyslogMessage {
+        facility: None,
+        severity: None,
+        timestamp: None,
+        hostname: None,
+        app_name: None,
+        proc_id: None,
+        msg_id: None,
+        message: String::new(),
+    };
+
+    let mut rest = rest;
+    if let Some(stamp) = rest.get(..15).and_then(bsd_timestamp) {
+        message.timestamp = Some(stamp);
+        rest = rest[15..].trim_start();
+    } else if let Some((token, tail)) = rest.split_once(' ') {
+        if let Ok(stamp) = DateTime::parse_from_rfc3339(token) {
+            message.timestamp = Some(stamp.with_timezone(&Utc));
+            rest = tail;
+        }
+    }
+
+    // The hostname is only present after a timestamp
+    if message.timestamp.is_some() {
+        if let Some((host, tail)) = rest.split_once(' ') {
+            if !host.ends_with(':') && !host.contains('|') {
+                message.hostname = Some(host.to_string());
+                rest = tail;
+            }
+        }
+    }
+
+    if let Some((tag, tail)) = rest.split_once(' ') {
+        if let Some(tag) = tag.strip_suffix(':') {
+            match tag.split_once('[') {
+                Some((app, pid)) => {
+                    message.app_name = Some(app.to_string());
+                    message.proc_id = Some(pid.trim_end_matches(']').to_string());
+                }
+                None => message.app_name = Some(tag.to_string()),
+            }
+            rest = tail;
+        }
+    }
+
+    message.message = rest.trim().to_string();
+    message
+}
+
+/// BSD timestamps carry no year; assume the current one, or last year if
+/// that would put the message more than a day in the future
+fn bsd_timestamp(stamp: &str) -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+    let parse = |year: i32| {
+        NaiveDateTime::parse_from_str(&format!("{} {}", year, stamp), "%Y %b %e %H:%M:%S")
+            .ok()
+            .and_then(|t| Utc.from_local_datetime(&t).single())
+    };
+    let stamp = parse(now.year())?;
+    if stamp > now + chrono::Duration::days(1) {
+        return parse(now.year() - 1);
+    }
+    Some(stamp)
+}
@@ -0,0 +1,285 @@
+//! Elasticsearch bulk API forwarder
+
+use super::delivery::{gzip, outcome_for_status, DeliveryConfig, DeliveryMetrics, DeliveryOutcome, DeliveryPipeline, DeliverySink};
+use super::mapping::FieldMapping;
+use super::{ElasticConfig, ElasticConnector, SiemConnector, SiemError, TimeRange};
+use crate::SecurityEvent;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct BulkConfig {
+    /// Cluster nodes; requests fail over between them
+    pub hosts: Vec<String>,
+    /// Data stream name, or index prefix for daily indices
+    pub index: String,
+    /// Write to a data stream instead of `<index>-YYYY.MM.DD`
+    pub data_stream: bool,
+    /// Ingest pipeline applied on the cluster
+    pub pipeline: Option<String>,
+    pub api_key: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub mapping: FieldMapping,
+    pub delivery: DeliveryConfig,
+}
+
+impl BulkConfig {
+    pub fn new(hosts: Vec<String>, index: &str) -> Self {
+        Self {
+            hosts: hosts.into_iter().map(|h| h.trim_end_matches('/').to_string()).collect(),
+            index: index.to_string(),
+            data_stream: false,
+            pipeline: None,
+            api_key: None,
+            username: None,
+            password: None,
+            mapping: FieldMapping::ecs(),
+            delivery: DeliveryConfig::default(),
+        }
+    }
+
+    pub fn data_stream(mut self) -> Self {
+        self.data_stream = true;
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: FieldMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    pub fn with_delivery(mut self, delivery: DeliveryConfig) -> Self {
+        self.delivery = delivery;
+        self
+    }
+}
+
+/// Batched, compressed, spooling forwarder to the Elasticsearch bulk API
+pub struct ElasticBulkForwarder {
+    pipeline: DeliveryPipeline,
+    search: ElasticConnector,
+}
+
+impl ElasticBulkForwarder {
+    /// Start the forwarder; must be called inside a Tokio runtime
+    pub fn start(config: BulkConfig) -> Result<Self, SiemError> {
+        if config.hosts.is_empty() {
+            return Err(SiemError::ConfigError("No hosts configured".to_string()));
+        }
+        let search = ElasticConnector::new(ElasticConfig {
+            hosts: config.hosts.clone(),
+            index_pattern: config.index.clone(),
+            api_key: config.api_key.clone(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            ssl_verify: true,
+        });
+        let sink = BulkSink::new(config.clone())?;
+        Ok(Self {
+            pipeline: DeliveryPipeline::start(Arc::new(sink), config.delivery)?,
+            search,
+        })
+    }
+}
+
+#[async_trait]
+impl SiemConnector for ElasticBulkForwarder {
+    fn name(&self) -> &str {
+        "elastic"
+    }
+
+    async fn send_event(&self, event: &SecurityEvent) -> Result<(), SiemError> {
+        self.pipeline.enqueue(event).await
+    }
+
+    async fn send_batch(&self, events: &[SecurityEvent]) -> Result<(), SiemError> {
+        for event in events {
+            self.pipeline.enqueue(event).await?;
+        }
+        Ok(())
+    }
+
+    async fn query(&self, query: &str, time_range: TimeRange) -> Result<Vec<serde_json::Value>, SiemError> {
+        self.search.query(query, time_range).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.pipeline.health_check().await
+    }
+
+    fn delivery_metrics(&self) -> Option<DeliveryMetrics> {
+        Some(self.pipeline.metrics())
+    }
+}
+
+struct BulkSink {
+    config: BulkConfig,
+    client: reqwest::Client,
+    /// Host to try first; advances past hosts that fail to connect
+    next_host: AtomicUsize,
+}
+
+#[derive(Deserialize)]
+struct BulkResponse {
+    errors: bool,
+    #[serde(default)]
+    items: Vec<std::collections::HashMap<String, BulkItem>>,
+}
+
+#[derive(Deserialize)]
+struct BulkItem {
+    status: u16,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+impl BulkSink {
+    fn new(config: BulkConfig) -> Result<Self, SiemError> {
+        let client = reqwest::Client::builder()
+            .timeout(config.delivery.request_timeout)
+            .build()
+            .map_err(|e| SiemError::ConfigError(e.to_string()))?;
+        Ok(Self { config, client, next_host: AtomicUsize::new(0) })
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(api_key) = &self.config.api_key {
+            request.header("Authorization", format!("ApiKey {}", api_key))
+        } else if let Some(username) = &self.config.username {
+            request.basic_auth(username, self.config.password.as_ref())
+        } else {
+            request
+        }
+    }
+
+    /// Split per-item results into retryable and rejected documents
+    fn partial(response: BulkResponse, bytes: u64) -> DeliveryOutcome {
+        let mut retry = Vec::new();
+        let mut rejected = 0;
+        for (index, item) in response.items.iter().enumerate() {
+            let Some(result) = item.values().next() else { continue };
+            match result.status {
+                200..=299 => {}
+                429 | 500..=599 => retry.push(index),
+                _ => {
+                    rejected += 1;
+                    tracing::debug!("Elastic rejected document: {:?}", result.error);
+                }
+            }
+        }
+        DeliveryOutcome::Partial { bytes, retry, rejected }
+    }
+}
+
+#[async_trait]
+impl DeliverySink for BulkSink {
+    fn name(&self) -> &str {
+        "elastic-bulk"
+    }
+
+    /// Mapped document plus the index it belongs in
+    fn map(&self, event: &SecurityEvent) -> serde_json::Value {
+        let index = if self.config.data_stream {
+            self.config.index.clone()
+        } else {
+            format!("{}-{}", self.config.index, event.timestamp.format("%Y.%m.%d"))
+        };
+        serde_json::json!({
+            "_index": index,
+            "doc": self.config.mapping.apply(event),
+        })
+    }
+
+    async fn deliver(&self, docs: &[serde_json::Value]) -> DeliveryOutcome {
+        // `create` works for both data streams and plain indices
+        let mut body = Vec::new();
+        for doc in docs {
+            let action = serde_json::json!({ "create": { "_index": doc["_index"] } });
+            let written = serde_json::to_writer(&mut body, &action)
+                .and_then(|_| {
+                    body.push(b'\n');
+                    serde_json::to_writer(&mut body, &doc["doc"])
+                });
+            if written.is_err() {
+                return DeliveryOutcome::Rejected("unserializable event".to_string());
+            }
+            body.push(b'\n');
+        }
+
+        let gzipped = self.config.delivery.gzip;
+        if gzipped {
+            match gzip(&body) {
+                Ok(compressed) => body = compressed,
+                Err(e) => return DeliveryOutcome::Rejected(e.to_string()),
+            }
+        }
+        let bytes = body.len() as u64;
+
+        let hosts = self.config.hosts.len();
+        let start = self.next_host.load(Ordering::Relaxed);
+        let mut last_error = String::new();
+        for offset in 0..hosts {
+            let host = &self.config.hosts[(start + offset) % hosts];
+            let mut request = self.client
+                .post(format!("{}/_bulk", host))
+                .header("Content-Type", "application/x-ndjson")
+                .body(body.clone());
+            if gzipped {
+                request = request.header("Content-Encoding", "gzip");
+            }
+            if let Some(pipeline) = &self.config.pipeline {
+                request = request.query(&[("pipeline", pipeline)]);
+            }
+
+            let response = match self.authorize(request).send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::debug!("Elastic host {} unreachable: {}", host, e);
+                    last_error = e.to_string();
+                    self.next_host.store((start + offset + 1) % hosts, Ordering::Relaxed);
+                    continue;
+                }
+            };
+
+            let status = response.status();
+            if !status.is_success() {
+                let text = response.text().await.unwrap_or_default();
+                return outcome_for_status(status, text, bytes);
+            }
+            return match response.json::<BulkResponse>().await {
+                Ok(result) if result.errors => Self::partial(result, bytes),
+                Ok(_) => DeliveryOutcome::Delivered { bytes },
+                // Accepted but unreadable; resending would duplicate
+                Err(e) => {
+                    tracing::warn!("Unreadable bulk response from {}: {}", host, e);
+                    DeliveryOutcome::Delivered { bytes }
+                }
+            };
+        }
+        DeliveryOutcome::Unavailable(last_error)
+    }
+
+    async fn health_check(&self) -> bool {
+        for host in &self.config.hosts {
+            let request = self.authorize(self.client.get(format!("{}/_cluster/health", host)));
+            let healthy = match request.send().await {
+                Ok(response) if response.status().is_success() => response.json::<serde_json::Value>().await
+                    .map(|h| h["status"] != "red")
+                    .unwrap_or(false),
+                _ => false,
+            };
+            if healthy {
+                return true;
+            }
+        }
+        false
+    }
+}
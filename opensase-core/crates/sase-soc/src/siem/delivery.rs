@@ -0,0 +1,373 @@
+//! Batched SIEM delivery
+//!
+//! Shared machinery behind the HEC and bulk forwarders: events are mapped
+//! into the destination's schema, queued, shipped in gzip-compressed batches
+//! by a background worker, retried with backoff and spooled to disk when the
+//! destination stays down. A full queue pushes back on the caller for
+//! `enqueue_timeout` before overflowing into the spool.
+
+use super::spool::Spool;
+use super::SiemError;
+use crate::SecurityEvent;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Batching, retry and spooling settings for one destination
+#[derive(Clone)]
+pub struct DeliveryConfig {
+    /// Events per request
+    pub batch_size: usize,
+    /// Ship a partial batch after this long
+    pub flush_interval: Duration,
+    /// Events held in memory before callers are pushed back on
+    pub queue_capacity: usize,
+    /// How long a caller waits on a full queue before the event overflows
+    /// to the spool
+    pub enqueue_timeout: Duration,
+    /// Attempts per batch before it is spooled
+    pub max_retries: u32,
+    /// First retry delay; doubles per attempt
+    pub retry_backoff: Duration,
+    /// gzip request bodies
+    pub gzip: bool,
+    /// Spool directory; without one, undeliverable batches are dropped
+    pub spool_dir: Option<PathBuf>,
+    /// Spool size cap
+    pub spool_max_bytes: u64,
+    /// HTTP request timeout
+    pub request_timeout: Duration,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            flush_interval: Duration::from_secs(2),
+            queue_capacity: 10_000,
+            enqueue_timeout: Duration::from_millis(100),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            gzip: true,
+            spool_dir: None,
+            spool_max_bytes: 512 * 1024 * 1024,
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Wire side of a destination
+#[async_trait]
+pub trait DeliverySink: Send + Sync {
+    fn name(&self) -> &str;
+    /// Map an event into the document the destination stores
+    fn map(&self, event: &SecurityEvent) -> serde_json::Value;
+    /// Send one batch of mapped documents
+    async fn deliver(&self, docs: &[serde_json::Value]) -> DeliveryOutcome;
+    async fn health_check(&self) -> bool;
+}
+
+/// Result of shipping one batch
+#[derive(Debug)]
+pub enum DeliveryOutcome {
+    Delivered { bytes: u64 },
+    /// Some documents failed: `retry` indexes are worth resending,
+    /// `rejected` were refused for good
+    Partial { bytes: u64, retry: Vec<usize>, rejected: usize },
+    /// The destination refused the whole batch (bad token, malformed)
+    Rejected(String),
+    /// Outage, throttling or server error; retry later
+    Unavailable(String),
+}
+
+/// Delivery counters for one destination
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeliveryMetrics {
+    pub destination: String,
+    pub events_queued: u64,
+    pub events_delivered: u64,
+    pub events_rejected: u64,
+    pub events_spooled: u64,
+    pub events_replayed: u64,
+    pub events_dropped: u64,
+    pub batches_sent: u64,
+    pub bytes_sent: u64,
+    pub retries: u64,
+    pub queue_depth: usize,
+    pub spool_bytes: u64,
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicU64,
+    delivered: AtomicU64,
+    rejected: AtomicU64,
+    spooled: AtomicU64,
+    replayed: AtomicU64,
+    dropped: AtomicU64,
+    batches: AtomicU64,
+    bytes: AtomicU64,
+    retries: AtomicU64,
+    last_success: parking_lot::RwLock<Option<chrono::DateTime<chrono::Utc>>>,
+    last_error: parking_lot::RwLock<Option<String>>,
+}
+
+impl Counters {
+    fn add(counter: &AtomicU64, n: usize) {
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+}
+
+/// Queue plus background worker shipping batches to one sink
+pub struct DeliveryPipeline {
+    tx: mpsc::Sender<serde_json::Value>,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    sink: Arc<dyn DeliverySink>,
+    spool: Option<Spool>,
+    config: DeliveryConfig,
+    counters: Counters,
+}
+
+impl DeliveryPipeline {
+    /// Start the worker; must be called inside a Tokio runtime
+    pub fn start(sink: Arc<dyn DeliverySink>, config: DeliveryConfig) -> Result<Self, SiemError> {
+        let spool = match &config.spool_dir {
+            Some(dir) => Some(Spool::open(dir.join(sink.name()), config.spool_max_bytes)?),
+            None => None,
+        };
+        let (tx, rx) = mpsc::channel(config.queue_capacity.max(1));
+        let shared = Arc::new(Shared { sink, spool, config, counters: Counters::default() });
+        tokio::spawn(shared.clone().run(rx));
+        Ok(Self { tx, shared })
+    }
+
+    /// Queue an event, waiting briefly when the queue is full
+    pub async fn enqueue(&self, event: &SecurityEvent) -> Result<(), SiemError> {
+        let doc = self.shared.sink.map(event);
+        let counters = &self.shared.counters;
+
+        let permit = match self.tx.try_reserve() {
+            Ok(permit) => Some(permit),
+            Err(mpsc::error::TrySendError::Full(())) => {
+                tokio::time::timeout(self.shared.config.enqueue_timeout, self.tx.reserve()).await
+                    .ok()
+                    .and_then(|r| r.ok())
+            }
+            Err(mpsc::error::TrySendError::Closed(())) => None,
+        };
+        if let Some(permit) = permit {
+            permit.send(doc);
+            Counters::add(&counters.queued, 1);
+            return Ok(());
+        }
+
+        // Queue still full: overflow to disk rather than block the pipeline
+        match &self.shared.spool {
+            Some(spool) if spool.write(std::slice::from_ref(&doc)).await.is_ok() => {
+                Counters::add(&counters.spooled, 1);
+                Ok(())
+            }
+            _ => {
+                Counters::add(&counters.dropped, 1);
+                Err(SiemError::ConnectionFailed(format!("{} delivery queue full", self.shared.sink.name())))
+            }
+        }
+    }
+
+    pub async fn health_check(&self) -> bool {
+        self.shared.sink.health_check().await
+    }
+
+    pub fn metrics(&self) -> DeliveryMetrics {
+        let c = &self.shared.counters;
+        DeliveryMetrics {
+            destination: self.shared.sink.name().to_string(),
+            events_queued: c.queued.load(Ordering::Relaxed),
+            events_delivered: c.delivered.load(Ordering::Relaxed),
+            events_rejected: c.rejected.load(Ordering::Relaxed),
+            events_spooled: c.spooled.load(Ordering::Relaxed),
+            events_replayed: c.replayed.load(Ordering::Relaxed),
+            events_dropped: c.dropped.load(Ordering::Relaxed),
+            batches_sent: c.batches.load(Ordering::Relaxed),
+            bytes_sent: c.bytes.load(Ordering::Relaxed),
+            retries: c.retries.load(Ordering::Relaxed),
+            queue_depth: self.shared.config.queue_capacity.max(1) - self.tx.capacity(),
+            spool_bytes: self.shared.spool.as_ref().map(|s| s.bytes()).unwrap_or(0),
+            last_success: *c.last_success.read(),
+            last_error: c.last_error.read().clone(),
+        }
+    }
+}
+
+impl Shared {
+    async fn run(self: Arc<Self>, mut rx: mpsc::Receiver<serde_json::Value>) {
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut ticker = tokio::time::interval(self.config.flush_interval);
+
+        loop {
+            tokio::select! {
+                doc = rx.recv() => match doc {
+                    Some(doc) => {
+                        batch.push(doc);
+                        if batch.len() < self.config.batch_size {
+                            continue;
+                        }
+                    }
+                    None => {
+                        // Pipeline dropped: ship what is left and stop
+                        if !batch.is_empty() {
+                            self.ship(std::mem::take(&mut batch)).await;
+                        }
+                        return;
+                    }
+                },
+                _ = ticker.tick() => {}
+            }
+
+            // Replay the spool only while the destination is accepting
+            let healthy = batch.is_empty() || self.ship(std::mem::take(&mut batch)).await;
+            if healthy {
+                self.replay().await;
+            }
+        }
+    }
+
+    /// Ship a batch, retrying with backoff; spools what is still undelivered
+    /// after the last attempt. Returns whether the destination accepted it.
+    async fn ship(&self, mut docs: Vec<serde_json::Value>) -> bool {
+        let mut attempt = 0;
+        loop {
+            if self.attempt(&mut docs).await {
+                return true;
+            }
+            if docs.is_empty() {
+                return false;
+            }
+            attempt += 1;
+            if attempt > self.config.max_retries {
+                self.spill(&docs).await;
+                return false;
+            }
+            Counters::add(&self.counters.retries, 1);
+            tokio::time::sleep(self.config.retry_backoff * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    /// One delivery attempt; leaves only the documents worth retrying in
+    /// `docs` and returns true once nothing is left to retry
+    async fn attempt(&self, docs: &mut Vec<serde_json::Value>) -> bool {
+        let c = &self.counters;
+        match self.sink.deliver(docs).await {
+            DeliveryOutcome::Delivered { bytes } => {
+                Counters::add(&c.delivered, docs.len());
+                Counters::add(&c.batches, 1);
+                c.bytes.fetch_add(bytes, Ordering::Relaxed);
+                *c.last_success.write() = Some(chrono::Utc::now());
+                docs.clear();
+                true
+            }
+            DeliveryOutcome::Partial { bytes, retry, rejected } => {
+                Counters::add(&c.delivered, docs.len() - retry.len() - rejected);
+                Counters::add(&c.rejected, rejected);
+                Counters::add(&c.batches, 1);
+                c.bytes.fetch_add(bytes, Ordering::Relaxed);
+                *c.last_success.write() = Some(chrono::Utc::now());
+                let mut index = 0;
+                docs.retain(|_| {
+                    let keep = retry.contains(&index);
+                    index += 1;
+                    keep
+                });
+                docs.is_empty()
+            }
+            DeliveryOutcome::Rejected(e) => {
+                tracing::warn!("{} rejected batch of {}: {}", self.sink.name(), docs.len(), e);
+                Counters::add(&c.rejected, docs.len());
+                *c.last_error.write() = Some(e);
+                docs.clear();
+                false
+            }
+            DeliveryOutcome::Unavailable(e) => {
+                tracing::debug!("{} unavailable: {}", self.sink.name(), e);
+                *c.last_error.write() = Some(e);
+                false
+            }
+        }
+    }
+
+    async fn spill(&self, docs: &[serde_json::Value]) {
+        let spooled = match &self.spool {
+            Some(spool) => match spool.write(docs).await {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::error!("{} spool write failed: {}", self.sink.name(), e);
+                    false
+                }
+            },
+            None => false,
+        };
+        if spooled {
+            Counters::add(&self.counters.spooled, docs.len());
+        } else {
+            tracing::warn!("{} dropped {} undeliverable events", self.sink.name(), docs.len());
+            Counters::add(&self.counters.dropped, docs.len());
+        }
+    }
+
+    /// Replay spooled segments oldest first until one fails
+    async fn replay(&self) {
+        let Some(spool) = &self.spool else { return };
+        while let Some((segment, mut docs)) = spool.head().await {
+            let total = docs.len();
+            let done = self.attempt(&mut docs).await;
+            let remaining = docs.len();
+            Counters::add(&self.counters.replayed, total - remaining);
+
+            let result = if done || remaining == 0 {
+                spool.remove(segment).await
+            } else {
+                spool.rewrite(segment, &docs).await
+            };
+            if let Err(e) = result {
+                tracing::error!("{} spool update failed: {}", self.sink.name(), e);
+                return;
+            }
+            if !done {
+                return;
+            }
+        }
+    }
+}
+
+/// gzip a request body
+pub(crate) fn gzip(body: &[u8]) -> Result<Vec<u8>, SiemError> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::fast());
+    encoder.write_all(body).map_err(|e| SiemError::SerializationError(e.to_string()))?;
+    encoder.finish().map_err(|e| SiemError::SerializationError(e.to_string()))
+}
+
+/// Classify an HTTP status from a destination
+pub(crate) fn outcome_for_status(status: reqwest::StatusCode, body: String, bytes: u64) -> DeliveryOutcome {
+    if status.is_success() {
+        DeliveryOutcome::Delivered { bytes }
+    } else if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+    {
+        DeliveryOutcome::Unavailable(format!("{}: {}", status, body))
+    } else {
+        DeliveryOutcome::Rejected(format!("{}: {}", status, body))
+    }
+}
@@ -0,0 +1,185 @@
+//! Splunk HTTP Event Collector forwarder
+
+use super::delivery::{gzip, outcome_for_status, DeliveryConfig, DeliveryMetrics, DeliveryOutcome, DeliveryPipeline, DeliverySink};
+use super::mapping::FieldMapping;
+use super::{SiemConnector, SiemError, SplunkConfig, SplunkConnector, TimeRange};
+use crate::SecurityEvent;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct HecConfig {
+    /// Collector base URL, e.g. `https://splunk.example.com:8088`
+    pub url: String,
+    pub token: String,
+    /// Target index; the token's default index when unset
+    pub index: Option<String>,
+    pub source_type: String,
+    pub source: String,
+    /// Request channel GUID, required when indexer acknowledgement is on
+    pub channel: Option<String>,
+    pub mapping: FieldMapping,
+    pub delivery: DeliveryConfig,
+}
+
+impl HecConfig {
+    pub fn new(url: &str, token: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            index: None,
+            source_type: "opensase:security".to_string(),
+            source: "opensase".to_string(),
+            channel: None,
+            mapping: FieldMapping::cim(),
+            delivery: DeliveryConfig::default(),
+        }
+    }
+
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    pub fn with_mapping(mut self, mapping: FieldMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    pub fn with_delivery(mut self, delivery: DeliveryConfig) -> Self {
+        self.delivery = delivery;
+        self
+    }
+}
+
+/// Batched, compressed, spooling forwarder to Splunk HEC
+pub struct SplunkHecForwarder {
+    pipeline: DeliveryPipeline,
+    /// Search side stays on the management API connector
+    search: SplunkConnector,
+}
+
+impl SplunkHecForwarder {
+    /// Start the forwarder; must be called inside a Tokio runtime
+    pub fn start(config: HecConfig, search: SplunkConfig) -> Result<Self, SiemError> {
+        let sink = HecSink::new(config.clone())?;
+        Ok(Self {
+            pipeline: DeliveryPipeline::start(Arc::new(sink), config.delivery)?,
+            search: SplunkConnector::new(search),
+        })
+    }
+}
+
+#[async_trait]
+impl SiemConnector for SplunkHecForwarder {
+    fn name(&self) -> &str {
+        "splunk"
+    }
+
+    async fn send_event(&self, event: &SecurityEvent) -> Result<(), SiemError> {
+        self.pipeline.enqueue(event).await
+    }
+
+    async fn send_batch(&self, events: &[SecurityEvent]) -> Result<(), SiemError> {
+        for event in events {
+            self.pipeline.enqueue(event).await?;
+        }
+        Ok(())
+    }
+
+    async fn query(&self, query: &str, time_range: TimeRange) -> Result<Vec<serde_json::Value>, SiemError> {
+        self.search.query(query, time_range).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.pipeline.health_check().await
+    }
+
+    fn delivery_metrics(&self) -> Option<DeliveryMetrics> {
+        Some(self.pipeline.metrics())
+    }
+}
+
+struct HecSink {
+    config: HecConfig,
+    client: reqwest::Client,
+}
+
+impl HecSink {
+    fn new(config: HecConfig) -> Result<Self, SiemError> {
+        let client = reqwest::Client::builder()
+            .timeout(config.delivery.request_timeout)
+            .build()
+            .map_err(|e| SiemError::ConfigError(e.to_string()))?;
+        Ok(Self { config, client })
+    }
+}
+
+#[async_trait]
+impl DeliverySink for HecSink {
+    fn name(&self) -> &str {
+        "splunk-hec"
+    }
+
+    /// HEC event envelope around the mapped fields
+    fn map(&self, event: &SecurityEvent) -> serde_json::Value {
+        let mut envelope = serde_json::json!({
+            "time": event.timestamp.timestamp_millis() as f64 / 1000.0,
+            "host": event.source.host.clone().unwrap_or_else(|| event.source.system.clone()),
+            "source": self.config.source,
+            "sourcetype": self.config.source_type,
+            "event": self.config.mapping.apply(event),
+        });
+        if let Some(index) = &self.config.index {
+            envelope["index"] = serde_json::json!(index);
+        }
+        envelope
+    }
+
+    async fn deliver(&self, docs: &[serde_json::Value]) -> DeliveryOutcome {
+        // HEC takes concatenated event objects in one body
+        let mut body = Vec::new();
+        for doc in docs {
+            if serde_json::to_writer(&mut body, doc).is_err() {
+                return DeliveryOutcome::Rejected("unserializable event".to_string());
+            }
+            body.push(b'\n');
+        }
+
+        let mut request = self.client
+            .post(format!("{}/services/collector/event", self.config.url))
+            .header("Authorization", format!("Splunk {}", self.config.token))
+            .header("Content-Type", "application/json");
+        if let Some(channel) = &self.config.channel {
+            request = request.header("X-Splunk-Request-Channel", channel);
+        }
+        if self.config.delivery.gzip {
+            match gzip(&body) {
+                Ok(compressed) => {
+                    body = compressed;
+                    request = request.header("Content-Encoding", "gzip");
+                }
+                Err(e) => return DeliveryOutcome::Rejected(e.to_string()),
+            }
+        }
+        let bytes = body.len() as u64;
+
+        match request.body(body).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                outcome_for_status(status, text, bytes)
+            }
+            Err(e) => DeliveryOutcome::Unavailable(e.to_string()),
+        }
+    }
+
+    async fn health_check(&self) -> bool {
+        self.client
+            .get(format!("{}/services/collector/health", self.config.url))
+            .send()
+            .await
+            .map(|r| r.status().is_success())
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,187 @@
+//! Per-destination field mapping
+//!
+//! Splunk searches expect CIM field names, Elastic dashboards expect ECS.
+//! A [`FieldMapping`] picks the base schema and layers destination-specific
+//! renames and static fields on top.
+
+use crate::{EventType, IndicatorType, SecurityEvent, Severity};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldSchema {
+    /// Splunk Common Information Model
+    Cim,
+    /// Elastic Common Schema
+    Ecs,
+    /// Native event serialization
+    Raw,
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldMapping {
+    pub schema: FieldSchema,
+    /// Dotted source path to dotted destination path, applied after the
+    /// schema mapping
+    pub renames: Vec<(String, String)>,
+    /// Fields set on every document, e.g. an environment label
+    pub static_fields: BTreeMap<String, Value>,
+}
+
+impl FieldMapping {
+    pub fn new(schema: FieldSchema) -> Self {
+        Self { schema, renames: Vec::new(), static_fields: BTreeMap::new() }
+    }
+
+    pub fn cim() -> Self {
+        Self::new(FieldSchema::Cim)
+    }
+
+    pub fn ecs() -> Self {
+        Self::new(FieldSchema::Ecs)
+    }
+
+    pub fn with_rename(mut self, from: &str, to: &str) -> Self {
+        self.renames.push((from.to_string(), to.to_string()));
+        self
+    }
+
+    pub fn with_field(mut self, path: &str, value: Value) -> Self {
+        self.static_fields.insert(path.to_string(), value);
+        self
+    }
+
+    /// Map an event into this destination's document
+    pub fn apply(&self, event: &SecurityEvent) -> Value {
+        let mut doc = match self.schema {
+            FieldSchema::Cim => to_cim(event),
+            FieldSchema::Ecs => to_ecs(event),
+            FieldSchema::Raw => serde_json::to_value(event).unwrap_or(Value::Null),
+        };
+        for (from, to) in &self.renames {
+            if let Some(value) = take_path(&mut doc, from) {
+                set_path(&mut doc, to, value);
+            }
+        }
+        for (path, value) in &self.static_fields {
+            set_path(&mut doc, path, value.clone());
+        }
+        doc
+    }
+}
+
+/// CIM fields covering the Intrusion Detection, Authentication, Malware and
+/// Data Loss Prevention data models
+pub fn to_cim(event: &SecurityEvent) -> Value {
+    let indicator = |kind: IndicatorType| {
+        event.indicators.iter().find(|i| i.indicator_type == kind).map(|i| i.value.clone())
+    };
+    let mut tags = event.tags.clone();
+    tags.extend(cim_tags(event.event_type).iter().map(|t| t.to_string()));
+
+    json!({
+        "_time": event.timestamp.timestamp_millis() as f64 / 1000.0,
+        "signature": format!("{:?}", event.event_type),
+        "signature_id": event.id,
+        "category": cim_category(event.event_type),
+        "severity": cim_severity(event.severity),
+        "severity_id": event.severity as u8,
+        "description": event.description,
+        "src": event.source.ip.clone().or_else(|| indicator(IndicatorType::IpAddress)),
+        "dvc": event.source.host,
+        "app": event.source.component,
+        "vendor_product": format!("OpenSASE {}", event.source.system),
+        "user": indicator(IndicatorType::Username),
+        "dest": indicator(IndicatorType::Domain),
+        "url": indicator(IndicatorType::Url),
+        "file_hash": indicator(IndicatorType::Hash),
+        "file_name": indicator(IndicatorType::FileName),
+        "process": indicator(IndicatorType::Process),
+        "tenant_id": event.tenant_id,
+        "tag": tags,
+    })
+}
+
+/// ECS document: the shared converter plus observer, labels and related
+/// indicators
+pub fn to_ecs(event: &SecurityEvent) -> Value {
+    let mut doc = crate::forwarder::to_ecs(event);
+    let related = |kinds: &[IndicatorType]| -> Vec<String> {
+        event.indicators.iter()
+            .filter(|i| kinds.contains(&i.indicator_type))
+            .map(|i| i.value.clone())
+            .collect()
+    };
+
+    set_path(&mut doc, "event.dataset", json!(format!("opensase.{}", event.source.system)));
+    set_path(&mut doc, "event.module", json!("opensase"));
+    set_path(&mut doc, "observer.vendor", json!("OpenSASE"));
+    set_path(&mut doc, "observer.product", json!(event.source.component));
+    set_path(&mut doc, "rule.name", json!(format!("{:?}", event.event_type)));
+    set_path(&mut doc, "related.ip", json!(related(&[IndicatorType::IpAddress])));
+    set_path(&mut doc, "related.user", json!(related(&[IndicatorType::Username, IndicatorType::Email])));
+    set_path(&mut doc, "related.hash", json!(related(&[IndicatorType::Hash])));
+    if let Some(tenant_id) = &event.tenant_id {
+        set_path(&mut doc, "labels.tenant_id", json!(tenant_id));
+    }
+    doc
+}
+
+fn cim_category(event_type: EventType) -> &'static str {
+    match event_type {
+        EventType::NetworkIntrusion | EventType::DdosAttack | EventType::PortScan
+        | EventType::SuspiciousTraffic | EventType::WebAttack | EventType::ApiAbuse
+        | EventType::BotActivity => "intrusion_detection",
+        EventType::MalwareDetected | EventType::SuspiciousProcess | EventType::FileIntegrity
+        | EventType::PrivilegeEscalation => "malware",
+        EventType::AuthenticationFailure | EventType::BruteForceAttempt
+        | EventType::ImpossibleTravel | EventType::AccountCompromise => "authentication",
+        EventType::DataExfiltration | EventType::DlpViolation
+        | EventType::UnauthorizedAccess => "data_loss_prevention",
+        EventType::PolicyViolation | EventType::ComplianceViolation | EventType::Custom => "alert",
+    }
+}
+
+fn cim_tags(event_type: EventType) -> &'static [&'static str] {
+    match cim_category(event_type) {
+        "intrusion_detection" => &["ids", "attack"],
+        "malware" => &["malware", "attack"],
+        "authentication" => &["authentication"],
+        "data_loss_prevention" => &["dlp", "incident"],
+        _ => &["alert"],
+    }
+}
+
+fn cim_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "informational",
+        Severity::Low => "low",
+        Severity::Medium => "medium",
+        Severity::High => "high",
+        Severity::Critical => "critical",
+    }
+}
+
+fn take_path(doc: &mut Value, path: &str) -> Option<Value> {
+    let (parent, leaf) = match path.rsplit_once('.') {
+        Some((parent, leaf)) => (parent.split('.').try_fold(&mut *doc, |v, k| v.get_mut(k))?, leaf),
+        None => (doc, path),
+    };
+    parent.as_object_mut()?.remove(leaf)
+}
+
+fn set_path(doc: &mut Value, path: &str, value: Value) {
+    let mut current = doc;
+    let mut parts = path.split('.').peekable();
+    while let Some(part) = parts.next() {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else { return };
+        if parts.peek().is_none() {
+            map.insert(part.to_string(), value);
+            return;
+        }
+        current = map.entry(part.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    }
+}
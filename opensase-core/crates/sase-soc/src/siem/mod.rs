@@ -1,6 +1,20 @@
 //! SIEM Integration
 //!
 //! Connectors for Splunk, Elastic, Sentinel, QRadar.
+//!
+//! The Splunk HEC and Elasticsearch bulk forwarders batch, compress and
+//! spool events on outage; see [`delivery`].
+
+mod bulk;
+pub mod delivery;
+mod hec;
+pub mod mapping;
+mod spool;
+
+pub use bulk::{BulkConfig, ElasticBulkForwarder};
+pub use delivery::{DeliveryConfig, DeliveryMetrics};
+pub use hec::{HecConfig, SplunkHecForwarder};
+pub use mapping::{FieldMapping, FieldSchema};
 
 use crate::{SecurityEvent, SecurityAlert, Severity};
 use async_trait::async_trait;
@@ -8,7 +22,7 @@ use async_trait::async_trait;
 /// SIEM integration hub
 pub struct SiemIntegration {
     /// Available connectors
-    connectors: dashmap::DashMap<String, Box<dyn SiemConnector>>,
+    connectors: dashmap::DashMap<String, std::sync::Arc<dyn SiemConnector>>,
     /// Event buffer
     event_buffer: dashmap::DashMap<String, Vec<SecurityEvent>>,
     /// Stats
@@ -27,6 +41,10 @@ pub trait SiemConnector: Send + Sync {
     async fn send_batch(&self, events: &[SecurityEvent]) -> Result<(), SiemError>;
     async fn query(&self, query: &str, time_range: TimeRange) -> Result<Vec<serde_json::Value>, SiemError>;
     async fn health_check(&self) -> bool;
+    /// Delivery counters for queued forwarders
+    fn delivery_metrics(&self) -> Option<DeliveryMetrics> {
+        None
+    }
 }
 
 #[derive(Clone)]
@@ -453,12 +471,14 @@ impl SiemIntegration {
     pub fn register(&self, connector: Box<dyn SiemConnector>) {
         let name = connector.name().to_string();
         tracing::info!("Registering SIEM connector: {}", name);
-        self.connectors.insert(name, connector);
+        self.connectors.insert(name, connector.into());
     }
     
     /// Forward event to all SIEMs
     pub async fn forward(&self, event: &SecurityEvent) {
-        for connector in self.connectors.iter() {
+        // Clone out of the map so no shard lock is held across a send
+        let connectors: Vec<_> = self.connectors.iter().map(|c| c.value().clone()).collect();
+        for connector in connectors {
             match connector.send_event(event).await {
                 Ok(_) => {
                     self.stats.events_forwarded.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -474,6 +494,7 @@ impl SiemIntegration {
     /// Query a specific SIEM
     pub async fn query(&self, siem: &str, query: &str, time_range: TimeRange) -> Result<Vec<serde_json::Value>, SiemError> {
         let connector = self.connectors.get(siem)
+            .map(|c| c.value().clone())
             .ok_or_else(|| SiemError::NotFound(siem.to_string()))?;
         connector.query(query, time_range).await
     }
@@ -482,6 +503,15 @@ impl SiemIntegration {
     pub async fn get_event_count(&self) -> u64 {
         self.stats.events_forwarded.load(std::sync::atomic::Ordering::Relaxed)
     }
+    
+    /// Per-destination delivery counters of queued forwarders
+    pub fn delivery_metrics(&self) -> Vec<DeliveryMetrics> {
+        let mut metrics: Vec<_> = self.connectors.iter()
+            .filter_map(|c| c.delivery_metrics())
+            .collect();
+        metrics.sort_by(|a, b| a.destination.cmp(&b.destination));
+        metrics
+    }
 }
 
 impl Default for SiemIntegration {
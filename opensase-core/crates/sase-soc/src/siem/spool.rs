@@ -0,0 +1,181 @@
+//! Disk spool for undeliverable SIEM batches
+//!
+//! Each segment is one NDJSON file named by sequence number. Segments
+//! survive restarts and are replayed oldest first; overflow appends to the
+//! newest segment unless it is the one being replayed.
+
+use super::SiemError;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Documents per segment before a new one is started
+const SEGMENT_DOCS: usize = 1000;
+
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    state: Mutex<SpoolState>,
+    /// Mirror of `SpoolState::bytes` readable without the async lock
+    bytes: std::sync::atomic::AtomicU64,
+}
+
+struct SpoolState {
+    segments: VecDeque<Segment>,
+    bytes: u64,
+    next_seq: u64,
+}
+
+#[derive(Clone)]
+struct Segment {
+    seq: u64,
+    docs: usize,
+    bytes: u64,
+}
+
+impl Spool {
+    /// Open a spool directory, picking up segments left by a previous run
+    pub fn open(dir: PathBuf, max_bytes: u64) -> Result<Self, SiemError> {
+        std::fs::create_dir_all(&dir).map_err(spool_error)?;
+
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(&dir).map_err(spool_error)? {
+            let path = entry.map_err(spool_error)?.path();
+            let seq = path.file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|_| path.extension().is_some_and(|e| e == "ndjson"))
+                .and_then(|s| s.parse::<u64>().ok());
+            if let Some(seq) = seq {
+                let content = std::fs::read_to_string(&path).map_err(spool_error)?;
+                segments.push(Segment {
+                    seq,
+                    docs: content.lines().filter(|l| !l.is_empty()).count(),
+                    bytes: content.len() as u64,
+                });
+            }
+        }
+        segments.sort_by_key(|s| s.seq);
+
+        let bytes = segments.iter().map(|s| s.bytes).sum();
+        let next_seq = segments.last().map(|s| s.seq + 1).unwrap_or(0);
+        if !segments.is_empty() {
+            tracing::info!("Recovered {} spooled SIEM segments from {}", segments.len(), dir.display());
+        }
+
+        Ok(Self {
+            dir,
+            max_bytes,
+            state: Mutex::new(SpoolState { segments: segments.into(), bytes, next_seq }),
+            bytes: std::sync::atomic::AtomicU64::new(bytes),
+        })
+    }
+
+    /// Bytes on disk
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Append documents; fails when the spool is full
+    pub async fn write(&self, docs: &[serde_json::Value]) -> Result<(), SiemError> {
+        let data = encode(docs)?;
+        let len = data.len() as u64;
+
+        let mut state = self.state.lock().await;
+        if state.bytes + len > self.max_bytes {
+            return Err(SiemError::ConfigError("spool full".to_string()));
+        }
+
+        // Never append to the head: replay may be reading it
+        let tail = state.segments.len() > 1
+            && state.segments.back().is_some_and(|s| s.docs + docs.len() <= SEGMENT_DOCS);
+        let seq = if tail {
+            state.segments.back().map(|s| s.seq).unwrap_or_default()
+        } else {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.segments.push_back(Segment { seq, docs: 0, bytes: 0 });
+            seq
+        };
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(seq))
+            .await
+            .map_err(spool_error)?;
+        file.write_all(&data).await.map_err(spool_error)?;
+        file.flush().await.map_err(spool_error)?;
+
+        if let Some(segment) = state.segments.iter_mut().find(|s| s.seq == seq) {
+            segment.docs += docs.len();
+            segment.bytes += len;
+        }
+        state.bytes += len;
+        self.sync_bytes(&state);
+        Ok(())
+    }
+
+    /// Oldest segment and its documents
+    pub async fn head(&self) -> Option<(u64, Vec<serde_json::Value>)> {
+        let seq = self.state.lock().await.segments.front()?.seq;
+        let content = tokio::fs::read_to_string(self.path(seq)).await.ok()?;
+        let docs = content.lines()
+            .filter(|l| !l.is_empty())
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect();
+        Some((seq, docs))
+    }
+
+    /// Drop a replayed segment
+    pub async fn remove(&self, seq: u64) -> Result<(), SiemError> {
+        let mut state = self.state.lock().await;
+        if let Some(pos) = state.segments.iter().position(|s| s.seq == seq) {
+            if let Some(segment) = state.segments.remove(pos) {
+                state.bytes = state.bytes.saturating_sub(segment.bytes);
+            }
+        }
+        self.sync_bytes(&state);
+        match tokio::fs::remove_file(self.path(seq)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(spool_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace a segment with the documents that still need delivery
+    pub async fn rewrite(&self, seq: u64, docs: &[serde_json::Value]) -> Result<(), SiemError> {
+        let data = encode(docs)?;
+        let mut state = self.state.lock().await;
+        tokio::fs::write(self.path(seq), &data).await.map_err(spool_error)?;
+        let mut freed = 0;
+        if let Some(segment) = state.segments.iter_mut().find(|s| s.seq == seq) {
+            freed = segment.bytes.saturating_sub(data.len() as u64);
+            segment.docs = docs.len();
+            segment.bytes = data.len() as u64;
+        }
+        state.bytes = state.bytes.saturating_sub(freed);
+        self.sync_bytes(&state);
+        Ok(())
+    }
+
+    fn path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.ndjson", seq))
+    }
+
+    fn sync_bytes(&self, state: &SpoolState) {
+        self.bytes.store(state.bytes, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+fn encode(docs: &[serde_json::Value]) -> Result<Vec<u8>, SiemError> {
+    let mut data = Vec::new();
+    for doc in docs {
+        serde_json::to_writer(&mut data, doc).map_err(|e| SiemError::SerializationError(e.to_string()))?;
+        data.push(b'\n');
+    }
+    Ok(data)
+}
+
+fn spool_error(e: std::io::Error) -> SiemError {
+    SiemError::ConfigError(format!("spool: {}", e))
+}
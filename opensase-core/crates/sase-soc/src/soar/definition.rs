@@ -0,0 +1,200 @@
+//! YAML playbook definitions
+//!
+//! ```yaml
+//! id: phishing-response
+//! name: Phishing Response
+//! triggers:
+//!   - alert_type: PhishingDetected
+//! conditions:
+//!   - { field: risk_score, operator: greater_than, value: "50" }
+//! steps:
+//!   - id: quarantine
+//!     action: { type: quarantine_email, message_field: message_id }
+//!     retries: 2
+//!     on_success: approve
+//!     on_failure: case
+//!   - id: approve
+//!     action: { type: approval, approvers: [soc-lead], timeout_secs: 1800 }
+//!     on_success: disable
+//!     on_failure: case
+//!   - id: disable
+//!     action: { type: disable_user, user_field: user }
+//!     on_success: case
+//!   - id: case
+//!     action: { type: create_case, template: phishing }
+//! ```
+
+use super::{Playbook, PlaybookAction};
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum PlaybookError {
+    Io(String),
+    Parse(String),
+    Invalid { playbook: String, reason: String },
+}
+
+impl std::fmt::Display for PlaybookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO error: {}", e),
+            Self::Parse(e) => write!(f, "Parse error: {}", e),
+            Self::Invalid { playbook, reason } => write!(f, "Invalid playbook {}: {}", playbook, reason),
+        }
+    }
+}
+
+impl std::error::Error for PlaybookError {}
+
+impl Playbook {
+    /// Parse and validate a YAML playbook
+    pub fn from_yaml(yaml: &str) -> Result<Self, PlaybookError> {
+        let playbook: Playbook = serde_yaml::from_str(yaml)
+            .map_err(|e| PlaybookError::Parse(e.to_string()))?;
+        playbook.validate()?;
+        Ok(playbook)
+    }
+
+    /// Render as YAML
+    pub fn to_yaml(&self) -> Result<String, PlaybookError> {
+        serde_yaml::to_string(self).map_err(|e| PlaybookError::Parse(e.to_string()))
+    }
+
+    /// Check step references and control-flow structure
+    pub fn validate(&self) -> Result<(), PlaybookError> {
+        let invalid = |reason: String| PlaybookError::Invalid { playbook: self.id.clone(), reason };
+
+        if self.id.is_empty() {
+            return Err(invalid("missing id".to_string()));
+        }
+        if self.triggers.is_empty() {
+            return Err(invalid("no triggers".to_string()));
+        }
+        if self.steps.is_empty() {
+            return Err(invalid("no steps".to_string()));
+        }
+
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if !ids.insert(step.id.as_str()) {
+                return Err(invalid(format!("duplicate step {}", step.id)));
+            }
+        }
+
+        let known = |id: &String, from: &str| {
+            if ids.contains(id.as_str()) {
+                Ok(())
+            } else {
+                Err(invalid(format!("step {} references unknown step {}", from, id)))
+            }
+        };
+        for step in &self.steps {
+            for next in step.on_success.iter().chain(&step.on_failure) {
+                known(next, &step.id)?;
+            }
+            match &step.action {
+                PlaybookAction::Conditional { then_step, else_step, .. } => {
+                    known(then_step, &step.id)?;
+                    if let Some(else_step) = else_step {
+                        known(else_step, &step.id)?;
+                    }
+                }
+                PlaybookAction::Parallel { steps } => {
+                    if steps.is_empty() {
+                        return Err(invalid(format!("parallel step {} has no branches", step.id)));
+                    }
+                    for branch in steps {
+                        known(branch, &step.id)?;
+                        let action = self.steps.iter()
+                            .find(|s| &s.id == branch)
+                            .map(|s| &s.action);
+                        // Branches run to completion side by side; they cannot
+                        // pause or fan out again
+                        if matches!(action, Some(PlaybookAction::Approval { .. } | PlaybookAction::Parallel { .. }
+                            | PlaybookAction::Conditional { .. }))
+                        {
+                            return Err(invalid(format!("step {} cannot run inside parallel step {}", branch, step.id)));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Load every `.yaml`/`.yml` playbook in a directory
+    pub fn load_dir(dir: &Path) -> Result<Vec<Self>, PlaybookError> {
+        let entries = std::fs::read_dir(dir).map_err(|e| PlaybookError::Io(e.to_string()))?;
+        let mut playbooks = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| PlaybookError::Io(e.to_string()))?.path();
+            if !path.extension().is_some_and(|e| e == "yaml" || e == "yml") {
+                continue;
+            }
+            let yaml = std::fs::read_to_string(&path).map_err(|e| PlaybookError::Io(e.to_string()))?;
+            playbooks.push(Self::from_yaml(&yaml)?);
+        }
+        playbooks.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(playbooks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHISHING: &str = r#"
+id: phishing-response
+name: Phishing Response
+triggers:
+  - alert_type: PhishingDetected
+steps:
+  - id: quarantine
+    action: { type: quarantine_email, message_field: message_id }
+    on_success: approve
+    on_failure: case
+  - id: approve
+    action: { type: approval, approvers: [soc-lead] }
+    on_success: case
+  - id: case
+    action: { type: create_case, template: phishing }
+"#;
+
+    fn reason(yaml: &str) -> String {
+        match Playbook::from_yaml(yaml) {
+            Err(PlaybookError::Invalid { reason, .. }) => reason,
+            other => panic!("expected an invalid playbook, got {:?}", other.map(|p| p.id)),
+        }
+    }
+
+    #[test]
+    fn test_from_yaml_round_trip() {
+        let playbook = Playbook::from_yaml(PHISHING).unwrap();
+        assert_eq!(playbook.steps.len(), 3);
+        assert!(playbook.enabled);
+        assert!(matches!(playbook.steps[1].action, PlaybookAction::Approval { timeout_secs: 3600, .. }));
+
+        let again = Playbook::from_yaml(&playbook.to_yaml().unwrap()).unwrap();
+        assert_eq!(again.steps[0].on_failure.as_deref(), Some("case"));
+    }
+
+    #[test]
+    fn test_validate_rejects_broken_flow() {
+        assert_eq!(
+            reason(&PHISHING.replace("on_failure: case", "on_failure: escalate")),
+            "step quarantine references unknown step escalate"
+        );
+        assert_eq!(reason(&PHISHING.replace("id: approve", "id: case")), "duplicate step case");
+        assert_eq!(
+            reason(&PHISHING.replace(
+                "{ type: create_case, template: phishing }",
+                "{ type: parallel, steps: [quarantine, approve] }"
+            )),
+            "step approve cannot run inside parallel step case"
+        );
+        assert!(matches!(Playbook::from_yaml("id: x\nname: x\nsteps: []"), Err(PlaybookError::Parse(_))));
+        assert_eq!(reason("id: x\nname: x\ntriggers: [manual]\nsteps: []"), "no steps");
+    }
+}
@@ -0,0 +1,1392 @@
+//! SOAR Engine
+//!
+//! Security Orchestration, Automation, and Response.
+//!
+//! Playbooks are defined in YAML (see [`definition`]) or in code. An alert
+//! matching a playbook's triggers and conditions starts an execution that
+//! walks the steps, branching on each step's outcome:
+//!
+//! ```text
+//!   step ──Completed/Skipped──► on_success
+//!     │
+//!     └──Failed/TimedOut──────► on_failure      (after `retries` attempts)
+//!
+//!   approval step ──► AwaitingApproval ──approve──► on_success
+//!                                      └─reject/expire─► on_failure
+//! ```
+//!
+//! Response actions run through pluggable backends ([`responders`]): IP
+//! blocks go to threat-intel distribution, user disables to ZTNA, email
+//! quarantine to the mail gateway, cases to the case manager and evidence
+//! collection to the forensics collector.
+
+pub mod definition;
+pub mod responders;
+
+pub use definition::PlaybookError;
+pub use responders::{CaseSink, ForensicsSink, IpBlocklist, MailQuarantine, UserAccessControl};
+
+use crate::forensics::{CollectionRequest, CollectionTarget, EvidenceType};
+use crate::{IndicatorType, SecurityAlert, SecurityEvent, Severity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// SOAR automation engine
+pub struct SoarEngine {
+    /// Playbooks
+    playbooks: dashmap::DashMap<String, Playbook>,
+    /// Running executions
+    executions: dashmap::DashMap<String, PlaybookExecution>,
+    /// Execution IDs per alert, oldest first
+    alert_executions: dashmap::DashMap<String, Vec<String>>,
+    /// Executions paused on an approval gate
+    approvals: dashmap::DashMap<String, PendingApproval>,
+    /// Action handlers overriding the built-ins, by action type
+    actions: dashmap::DashMap<String, Arc<dyn ActionHandler>>,
+    /// Built-in response backends
+    ip_blocklist: Option<Arc<dyn IpBlocklist>>,
+    user_access: Option<Arc<dyn UserAccessControl>>,
+    mail_quarantine: Option<Arc<dyn MailQuarantine>>,
+    case_sink: Option<Arc<dyn CaseSink>>,
+    forensics: Option<Arc<dyn ForensicsSink>>,
+    /// Stats
+    execution_count: std::sync::atomic::AtomicU64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Playbook {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Any trigger starts the playbook; YAML writes each as a one-key map
+    /// (`- alert_type: PhishingDetected`) rather than a `!tag`
+    #[serde(with = "serde_yaml::with::singleton_map_recursive")]
+    pub triggers: Vec<PlaybookTrigger>,
+    /// All must hold for a triggered alert
+    #[serde(default)]
+    pub conditions: Vec<StepCondition>,
+    pub steps: Vec<PlaybookStep>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_playbook_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "chrono::Utc::now")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybookTrigger {
+    AlertType(String),
+    Severity(Severity),
+    MitreAttack {
+        #[serde(default)]
+        tactics: Vec<String>,
+        #[serde(default)]
+        techniques: Vec<String>,
+    },
+    Indicator { indicator_type: String },
+    Schedule { cron: String },
+    Manual,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaybookStep {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub action: PlaybookAction,
+    #[serde(default)]
+    pub condition: Option<StepCondition>,
+    #[serde(default)]
+    pub on_success: Option<String>,
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    #[serde(default = "default_step_timeout")]
+    pub timeout_secs: u64,
+    /// Extra attempts after a failure or timeout
+    #[serde(default)]
+    pub retries: u32,
+    /// Delay before the first retry; grows linearly per attempt
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay_secs: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlaybookAction {
+    // Enrichment
+    EnrichIndicator { types: Vec<String> },
+    LookupAsset { by: String },
+    LookupUser { by: String },
+    QuerySiem { query: String },
+
+    // Response
+    BlockIp {
+        ip_field: String,
+        /// Block lifetime; permanent when unset
+        #[serde(default)]
+        ttl_secs: Option<u64>,
+    },
+    IsolateHost { host_field: String },
+    DisableUser { user_field: String },
+    QuarantineFile { hash_field: String },
+    QuarantineEmail { message_field: String },
+
+    // Notification
+    SendEmail { recipients: Vec<String>, template: String },
+    SendSlack { channel: String, template: String },
+    CreateTicket { system: String, template: String },
+    PageOnCall { team: String },
+
+    // Case management
+    CreateCase { template: String },
+    UpdateCase { field: String, value: String },
+    EscalateCase { to: String },
+
+    // Forensics
+    CollectEvidence {
+        evidence_types: Vec<EvidenceType>,
+        /// How far back captures and snapshots reach
+        #[serde(default = "default_evidence_lookback")]
+        lookback_secs: u64,
+    },
+
+    // Custom
+    RunScript {
+        script: String,
+        #[serde(default)]
+        args: HashMap<String, String>,
+    },
+    CallApi { url: String, method: String, body: Option<String> },
+
+    // Control flow
+    Wait { seconds: u64 },
+    Parallel { steps: Vec<String> },
+    Conditional { condition: String, then_step: String, else_step: Option<String> },
+    /// Human approval gate: pauses the execution until an approver decides
+    Approval {
+        #[serde(default)]
+        approvers: Vec<String>,
+        #[serde(default)]
+        message: String,
+        #[serde(default = "default_approval_timeout")]
+        timeout_secs: u64,
+    },
+}
+
+impl PlaybookAction {
+    /// Action type name, as used in YAML and for handler registration
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::EnrichIndicator { .. } => "enrich_indicator",
+            Self::LookupAsset { .. } => "lookup_asset",
+            Self::LookupUser { .. } => "lookup_user",
+            Self::QuerySiem { .. } => "query_siem",
+            Self::BlockIp { .. } => "block_ip",
+            Self::IsolateHost { .. } => "isolate_host",
+            Self::DisableUser { .. } => "disable_user",
+            Self::QuarantineFile { .. } => "quarantine_file",
+            Self::QuarantineEmail { .. } => "quarantine_email",
+            Self::SendEmail { .. } => "send_email",
+            Self::SendSlack { .. } => "send_slack",
+            Self::CreateTicket { .. } => "create_ticket",
+            Self::PageOnCall { .. } => "page_on_call",
+            Self::CreateCase { .. } => "create_case",
+            Self::UpdateCase { .. } => "update_case",
+            Self::EscalateCase { .. } => "escalate_case",
+            Self::CollectEvidence { .. } => "collect_evidence",
+            Self::RunScript { .. } => "run_script",
+            Self::CallApi { .. } => "call_api",
+            Self::Wait { .. } => "wait",
+            Self::Parallel { .. } => "parallel",
+            Self::Conditional { .. } => "conditional",
+            Self::Approval { .. } => "approval",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StepCondition {
+    /// Dotted path into the execution context, e.g. `alert.severity`
+    pub field: String,
+    pub operator: ConditionOperator,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOperator {
+    Equals,
+    NotEquals,
+    Contains,
+    GreaterThan,
+    LessThan,
+    Exists,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlaybookExecution {
+    pub id: String,
+    pub playbook_id: String,
+    pub alert_id: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub status: ExecutionStatus,
+    pub current_step: Option<String>,
+    pub step_results: HashMap<String, StepResult>,
+    /// Step IDs in the order they ran
+    pub path: Vec<String>,
+    pub context: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ExecutionStatus {
+    Running,
+    AwaitingApproval,
+    Completed,
+    Failed,
+    TimedOut,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StepResult {
+    pub step_id: String,
+    pub status: StepStatus,
+    pub output: serde_json::Value,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub attempts: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Skipped,
+    TimedOut,
+    AwaitingApproval,
+}
+
+/// Execution paused on an approval step
+#[derive(Clone, Debug, Serialize)]
+pub struct PendingApproval {
+    pub execution_id: String,
+    pub playbook_id: String,
+    pub alert_id: String,
+    pub step_id: String,
+    pub approvers: Vec<String>,
+    pub message: String,
+    pub requested_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[async_trait::async_trait]
+pub trait ActionHandler: Send + Sync {
+    fn action_type(&self) -> &str;
+    async fn execute(
+        &self,
+        action: &PlaybookAction,
+        context: &mut HashMap<String, serde_json::Value>,
+    ) -> Result<serde_json::Value, ActionError>;
+}
+
+fn default_enabled() -> bool { true }
+fn default_playbook_timeout() -> u64 { 300 }
+fn default_step_timeout() -> u64 { 30 }
+fn default_retry_delay() -> u64 { 5 }
+fn default_approval_timeout() -> u64 { 3600 }
+fn default_evidence_lookback() -> u64 { 900 }
+
+impl PlaybookStep {
+    fn new(id: &str, name: &str, action: PlaybookAction) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            action,
+            condition: None,
+            on_success: None,
+            on_failure: None,
+            timeout_secs: default_step_timeout(),
+            retries: 0,
+            retry_delay_secs: default_retry_delay(),
+        }
+    }
+
+    fn then(mut self, on_success: &str, on_failure: &str) -> Self {
+        self.on_success = Some(on_success.to_string());
+        self.on_failure = Some(on_failure.to_string());
+        self
+    }
+}
+
+impl SoarEngine {
+    pub fn new() -> Self {
+        let engine = Self {
+            playbooks: dashmap::DashMap::new(),
+            executions: dashmap::DashMap::new(),
+            alert_executions: dashmap::DashMap::new(),
+            approvals: dashmap::DashMap::new(),
+            actions: dashmap::DashMap::new(),
+            ip_blocklist: None,
+            user_access: None,
+            mail_quarantine: None,
+            case_sink: None,
+            forensics: None,
+            execution_count: std::sync::atomic::AtomicU64::new(0),
+        };
+
+        engine.load_default_playbooks();
+        engine
+    }
+
+    /// Block IPs through threat-intel distribution
+    pub fn with_ip_blocklist(mut self, blocklist: Arc<dyn IpBlocklist>) -> Self {
+        self.ip_blocklist = Some(blocklist);
+        self
+    }
+
+    /// Disable users through ZTNA
+    pub fn with_user_access(mut self, access: Arc<dyn UserAccessControl>) -> Self {
+        self.user_access = Some(access);
+        self
+    }
+
+    /// Quarantine messages through the email gateway
+    pub fn with_mail_quarantine(mut self, quarantine: Arc<dyn MailQuarantine>) -> Self {
+        self.mail_quarantine = Some(quarantine);
+        self
+    }
+
+    /// Open cases in the case manager
+    pub fn with_case_sink(mut self, cases: Arc<dyn CaseSink>) -> Self {
+        self.case_sink = Some(cases);
+        self
+    }
+
+    /// Collect evidence through the forensics collector
+    pub fn with_forensics(mut self, forensics: Arc<dyn ForensicsSink>) -> Self {
+        self.forensics = Some(forensics);
+        self
+    }
+
+    fn load_default_playbooks(&self) {
+        // Malware response playbook
+        self.register_playbook(Playbook {
+            id: "malware-response".to_string(),
+            name: "Malware Response".to_string(),
+            description: "Automated malware incident response".to_string(),
+            triggers: vec![PlaybookTrigger::AlertType("MalwareDetected".to_string())],
+            conditions: vec![],
+            steps: vec![
+                PlaybookStep::new("enrich", "Enrich Indicators", PlaybookAction::EnrichIndicator {
+                    types: vec!["hash".to_string(), "ip".to_string()]
+                }).then("isolate", "notify"),
+                PlaybookStep {
+                    condition: Some(StepCondition {
+                        field: "severity".to_string(),
+                        operator: ConditionOperator::GreaterThan,
+                        value: "Medium".to_string(),
+                    }),
+                    timeout_secs: 60,
+                    ..PlaybookStep::new("isolate", "Isolate Host", PlaybookAction::IsolateHost {
+                        host_field: "source_host".to_string()
+                    }).then("case", "notify")
+                },
+                PlaybookStep::new("case", "Create Case", PlaybookAction::CreateCase {
+                    template: "malware-incident".to_string()
+                }).then("notify", "notify"),
+                PlaybookStep {
+                    timeout_secs: 10,
+                    ..PlaybookStep::new("notify", "Notify Team", PlaybookAction::SendSlack {
+                        channel: "#security-alerts".to_string(),
+                        template: "malware-alert".to_string(),
+                    })
+                },
+            ],
+            enabled: true,
+            timeout_secs: 300,
+            created_at: chrono::Utc::now(),
+        });
+
+        // Brute force response playbook
+        self.register_playbook(Playbook {
+            id: "brute-force-response".to_string(),
+            name: "Brute Force Response".to_string(),
+            description: "Block brute force attempts".to_string(),
+            triggers: vec![PlaybookTrigger::AlertType("BruteForceAttempt".to_string())],
+            conditions: vec![],
+            steps: vec![
+                PlaybookStep {
+                    retries: 2,
+                    ..PlaybookStep::new("block", "Block IP", PlaybookAction::BlockIp {
+                        ip_field: "source_ip".to_string(),
+                        ttl_secs: Some(86400),
+                    }).then("notify", "notify")
+                },
+                PlaybookStep {
+                    timeout_secs: 10,
+                    ..PlaybookStep::new("notify", "Notify", PlaybookAction::SendEmail {
+                        recipients: vec!["security@example.com".to_string()],
+                        template: "brute-force-blocked".to_string(),
+                    })
+                },
+            ],
+            enabled: true,
+            timeout_secs: 120,
+            created_at: chrono::Utc::now(),
+        });
+
+        // Account compromise: disabling a user needs an analyst's sign-off
+        self.register_playbook(Playbook {
+            id: "account-compromise-response".to_string(),
+            name: "Account Compromise Response".to_string(),
+            description: "Open a case and disable the account after approval".to_string(),
+            triggers: vec![PlaybookTrigger::AlertType("AccountCompromise".to_string())],
+            conditions: vec![],
+            steps: vec![
+                PlaybookStep::new("case", "Create Case", PlaybookAction::CreateCase {
+                    template: "account-compromise".to_string(),
+                }).then("approve", "approve"),
+                PlaybookStep::new("approve", "Approve Disable", PlaybookAction::Approval {
+                    approvers: vec![],
+                    message: "Disable the compromised account?".to_string(),
+                    timeout_secs: default_approval_timeout(),
+                }).then("disable", "notify"),
+                PlaybookStep {
+                    retries: 2,
+                    ..PlaybookStep::new("disable", "Disable User", PlaybookAction::DisableUser {
+                        user_field: "user".to_string(),
+                    }).then("notify", "notify")
+                },
+                PlaybookStep::new("notify", "Notify", PlaybookAction::SendSlack {
+                    channel: "#security-alerts".to_string(),
+                    template: "account-compromise".to_string(),
+                }),
+            ],
+            enabled: true,
+            timeout_secs: 2 * default_approval_timeout(),
+            created_at: chrono::Utc::now(),
+        });
+    }
+
+    /// Register playbook
+    pub fn register_playbook(&self, playbook: Playbook) {
+        tracing::info!("Registering playbook: {} ({})", playbook.name, playbook.id);
+        self.playbooks.insert(playbook.id.clone(), playbook);
+    }
+
+    /// Parse, validate and register a YAML playbook
+    pub fn load_playbook_yaml(&self, yaml: &str) -> Result<String, PlaybookError> {
+        let playbook = Playbook::from_yaml(yaml)?;
+        let id = playbook.id.clone();
+        self.register_playbook(playbook);
+        Ok(id)
+    }
+
+    /// Register a handler that overrides the built-in for its action type
+    pub fn register_action(&self, handler: Arc<dyn ActionHandler>) {
+        self.actions.insert(handler.action_type().to_string(), handler);
+    }
+
+    /// Get playbook
+    pub fn get_playbook(&self, id: &str) -> Option<Playbook> {
+        self.playbooks.get(id).map(|p| p.clone())
+    }
+
+    /// Trigger playbooks for alert; returns the started execution IDs
+    pub async fn trigger(&self, alert: &SecurityAlert) -> Vec<String> {
+        self.trigger_with_context(alert, alert_context(alert, None)).await
+    }
+
+    /// Trigger playbooks for an alert with its source event's fields
+    /// (source IP, host, user, message ID) in the context
+    pub async fn trigger_for_event(&self, alert: &SecurityAlert, event: &SecurityEvent) -> Vec<String> {
+        self.trigger_with_context(alert, alert_context(alert, Some(event))).await
+    }
+
+    async fn trigger_with_context(
+        &self,
+        alert: &SecurityAlert,
+        context: HashMap<String, serde_json::Value>,
+    ) -> Vec<String> {
+        let matching = self.find_matching_playbooks(alert, &context);
+        let mut started = Vec::new();
+
+        for playbook in matching {
+            if !playbook.enabled {
+                continue;
+            }
+
+            tracing::info!(
+                "Triggering playbook {} for alert {}",
+                playbook.name, alert.id
+            );
+
+            let execution = self.execute_playbook(&playbook, alert, context.clone()).await;
+            started.push(execution.id);
+        }
+        started
+    }
+
+    fn find_matching_playbooks(
+        &self,
+        alert: &SecurityAlert,
+        context: &HashMap<String, serde_json::Value>,
+    ) -> Vec<Playbook> {
+        self.playbooks.iter()
+            .filter(|p| p.triggers.iter().any(|t| self.trigger_matches(t, alert)))
+            .filter(|p| p.conditions.iter().all(|c| self.evaluate_condition(c, context)))
+            .map(|p| p.clone())
+            .collect()
+    }
+
+    fn trigger_matches(&self, trigger: &PlaybookTrigger, alert: &SecurityAlert) -> bool {
+        match trigger {
+            PlaybookTrigger::AlertType(t) => &alert.alert_type == t,
+            PlaybookTrigger::Severity(s) => alert.severity >= *s,
+            PlaybookTrigger::MitreAttack { tactics, techniques } => {
+                tactics.iter().any(|t| alert.mitre_tactics.contains(t)) ||
+                techniques.iter().any(|t| alert.mitre_techniques.contains(t))
+            }
+            PlaybookTrigger::Manual => false,
+            PlaybookTrigger::Schedule { .. } => false,
+            PlaybookTrigger::Indicator { .. } => false,
+        }
+    }
+
+    /// Run a playbook against an alert regardless of its triggers
+    pub async fn run_manual(&self, playbook_id: &str, alert: &SecurityAlert) -> Option<PlaybookExecution> {
+        let playbook = self.get_playbook(playbook_id)?;
+        Some(self.execute_playbook(&playbook, alert, alert_context(alert, None)).await)
+    }
+
+    async fn execute_playbook(
+        &self,
+        playbook: &Playbook,
+        alert: &SecurityAlert,
+        context: HashMap<String, serde_json::Value>,
+    ) -> PlaybookExecution {
+        let execution = PlaybookExecution {
+            id: uuid::Uuid::new_v4().to_string(),
+            playbook_id: playbook.id.clone(),
+            alert_id: alert.id.clone(),
+            started_at: chrono::Utc::now(),
+            ended_at: None,
+            status: ExecutionStatus::Running,
+            current_step: playbook.steps.first().map(|s| s.id.clone()),
+            step_results: HashMap::new(),
+            path: Vec::new(),
+            context,
+        };
+
+        self.executions.insert(execution.id.clone(), execution.clone());
+        self.alert_executions.entry(alert.id.clone())
+            .or_default()
+            .push(execution.id.clone());
+        self.execution_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        let first = playbook.steps.first().map(|s| s.id.clone());
+        self.run(playbook, execution, first).await
+    }
+
+    /// Walk steps from `next` until the playbook ends, times out or pauses
+    /// on an approval gate
+    async fn run(
+        &self,
+        playbook: &Playbook,
+        mut execution: PlaybookExecution,
+        mut next: Option<String>,
+    ) -> PlaybookExecution {
+        let deadline = execution.started_at + chrono::Duration::seconds(playbook.timeout_secs as i64);
+        let mut failed = false;
+
+        while let Some(step_id) = next.take() {
+            let Some(step) = playbook.steps.iter().find(|s| s.id == step_id) else {
+                tracing::warn!("Playbook {} references unknown step {}", playbook.id, step_id);
+                failed = true;
+                break;
+            };
+
+            let remaining = (deadline - chrono::Utc::now()).to_std().unwrap_or_default();
+            if remaining.is_zero() {
+                execution.status = ExecutionStatus::TimedOut;
+                break;
+            }
+
+            execution.current_step = Some(step.id.clone());
+            execution.path.push(step.id.clone());
+
+            // Approval gates pause the execution until someone decides
+            if let PlaybookAction::Approval { approvers, message, timeout_secs } = &step.action {
+                let skipped = step.condition.as_ref()
+                    .is_some_and(|c| !self.evaluate_condition(c, &execution.context));
+                if !skipped {
+                    self.await_approval(playbook, &mut execution, step, approvers, message, *timeout_secs);
+                    return execution;
+                }
+            }
+
+            let result = match &step.action {
+                PlaybookAction::Parallel { steps } => {
+                    self.execute_parallel(playbook, step, steps, &mut execution, remaining).await
+                }
+                _ => self.execute_step(step, &mut execution.context, remaining).await,
+            };
+
+            next = match &step.action {
+                PlaybookAction::Conditional { then_step, else_step, .. } if result.status == StepStatus::Completed => {
+                    if result.output["branch"] == "then" { Some(then_step.clone()) } else { else_step.clone() }
+                }
+                _ => match result.status {
+                    StepStatus::Completed | StepStatus::Skipped => step.on_success.clone(),
+                    _ => step.on_failure.clone(),
+                },
+            };
+            failed = matches!(result.status, StepStatus::Failed | StepStatus::TimedOut) && next.is_none();
+            record_output(&mut execution.context, &result);
+            execution.step_results.insert(step.id.clone(), result);
+            self.save(&execution);
+        }
+
+        if execution.status == ExecutionStatus::Running {
+            execution.status = if failed { ExecutionStatus::Failed } else { ExecutionStatus::Completed };
+        }
+        execution.current_step = None;
+        execution.ended_at = Some(chrono::Utc::now());
+        self.save(&execution);
+        execution
+    }
+
+    fn await_approval(
+        &self,
+        playbook: &Playbook,
+        execution: &mut PlaybookExecution,
+        step: &PlaybookStep,
+        approvers: &[String],
+        message: &str,
+        timeout_secs: u64,
+    ) {
+        let now = chrono::Utc::now();
+        let approval = PendingApproval {
+            execution_id: execution.id.clone(),
+            playbook_id: playbook.id.clone(),
+            alert_id: execution.alert_id.clone(),
+            step_id: step.id.clone(),
+            approvers: approvers.to_vec(),
+            message: message.to_string(),
+            requested_at: now,
+            expires_at: now + chrono::Duration::seconds(timeout_secs as i64),
+        };
+        tracing::info!(
+            "Playbook {} awaiting approval at step {} for alert {}",
+            playbook.id, step.id, execution.alert_id
+        );
+
+        execution.status = ExecutionStatus::AwaitingApproval;
+        execution.step_results.insert(step.id.clone(), StepResult {
+            step_id: step.id.clone(),
+            status: StepStatus::AwaitingApproval,
+            output: serde_json::json!({ "approvers": approvers, "expires_at": approval.expires_at }),
+            error: None,
+            duration_ms: 0,
+            attempts: 0,
+        });
+        self.approvals.insert(execution.id.clone(), approval);
+        self.save(execution);
+    }
+
+    /// Executions waiting on an approval gate
+    pub fn pending_approvals(&self) -> Vec<PendingApproval> {
+        let mut pending: Vec<_> = self.approvals.iter().map(|a| a.clone()).collect();
+        pending.sort_by_key(|a| a.requested_at);
+        pending
+    }
+
+    /// Approve or reject a paused execution and resume it down the matching
+    /// branch
+    pub async fn decide_approval(
+        &self,
+        execution_id: &str,
+        approver: &str,
+        approved: bool,
+        comment: Option<&str>,
+    ) -> Result<PlaybookExecution, ActionError> {
+        let pending = self.approvals.get(execution_id)
+            .map(|a| a.clone())
+            .ok_or_else(|| ActionError::new("No pending approval for execution"))?;
+        if !pending.approvers.is_empty() && !pending.approvers.iter().any(|a| a == approver) {
+            return Err(ActionError::new(format!("{} is not an approver for this step", approver)));
+        }
+        self.approvals.remove(execution_id);
+
+        let decision = serde_json::json!({
+            "approved": approved,
+            "approver": approver,
+            "comment": comment,
+            "decided_at": chrono::Utc::now(),
+        });
+        let status = if approved { StepStatus::Completed } else { StepStatus::Failed };
+        let error = (!approved).then(|| format!("Rejected by {}", approver));
+        Ok(self.resume(&pending, status, decision, error).await)
+    }
+
+    /// Reject approvals that passed their deadline; returns the executions
+    /// resumed down their failure branch
+    pub async fn expire_approvals(&self) -> Vec<PlaybookExecution> {
+        let now = chrono::Utc::now();
+        let expired: Vec<PendingApproval> = self.approvals.iter()
+            .filter(|a| a.expires_at <= now)
+            .map(|a| a.clone())
+            .collect();
+
+        let mut resumed = Vec::new();
+        for pending in expired {
+            if self.approvals.remove(&pending.execution_id).is_none() {
+                continue;
+            }
+            let output = serde_json::json!({ "approved": false, "expired_at": now });
+            resumed.push(self.resume(&pending, StepStatus::TimedOut, output, Some("Approval expired".to_string())).await);
+        }
+        resumed
+    }
+
+    async fn resume(
+        &self,
+        pending: &PendingApproval,
+        status: StepStatus,
+        output: serde_json::Value,
+        error: Option<String>,
+    ) -> PlaybookExecution {
+        let mut execution = match self.get_execution(&pending.execution_id) {
+            Some(execution) => execution,
+            None => unreachable_execution(pending),
+        };
+        let result = StepResult {
+            step_id: pending.step_id.clone(),
+            status,
+            output,
+            error,
+            duration_ms: (chrono::Utc::now() - pending.requested_at).num_milliseconds().max(0) as u64,
+            attempts: 1,
+        };
+        record_output(&mut execution.context, &result);
+        execution.step_results.insert(pending.step_id.clone(), result);
+        execution.status = ExecutionStatus::Running;
+
+        let Some(playbook) = self.get_playbook(&pending.playbook_id) else {
+            execution.status = ExecutionStatus::Failed;
+            execution.ended_at = Some(chrono::Utc::now());
+            self.save(&execution);
+            return execution;
+        };
+        let step = playbook.steps.iter().find(|s| s.id == pending.step_id);
+        let next = step.and_then(|s| match status {
+            StepStatus::Completed => s.on_success.clone(),
+            _ => s.on_failure.clone(),
+        });
+        self.run(&playbook, execution, next).await
+    }
+
+    /// Cancel a running or paused execution
+    pub fn cancel(&self, execution_id: &str) -> bool {
+        self.approvals.remove(execution_id);
+        match self.executions.get_mut(execution_id) {
+            Some(mut execution) if matches!(execution.status, ExecutionStatus::Running | ExecutionStatus::AwaitingApproval) => {
+                execution.status = ExecutionStatus::Cancelled;
+                execution.ended_at = Some(chrono::Utc::now());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    async fn execute_parallel(
+        &self,
+        playbook: &Playbook,
+        step: &PlaybookStep,
+        branch_ids: &[String],
+        execution: &mut PlaybookExecution,
+        remaining: std::time::Duration,
+    ) -> StepResult {
+        let start = std::time::Instant::now();
+        let branches: Vec<&PlaybookStep> = branch_ids.iter()
+            .filter_map(|id| playbook.steps.iter().find(|s| &s.id == id))
+            .collect();
+
+        // Each branch works on its own copy of the context
+        let mut contexts = vec![execution.context.clone(); branches.len()];
+        let results = futures::future::join_all(
+            branches.iter().zip(contexts.iter_mut())
+                .map(|(branch, context)| self.execute_step(branch, context, remaining))
+        ).await;
+
+        let mut failures = Vec::new();
+        for result in results {
+            if matches!(result.status, StepStatus::Failed | StepStatus::TimedOut) {
+                failures.push(result.step_id.clone());
+            }
+            execution.path.push(result.step_id.clone());
+            record_output(&mut execution.context, &result);
+            execution.step_results.insert(result.step_id.clone(), result);
+        }
+        for context in contexts {
+            for (key, value) in context {
+                if key != "steps" {
+                    execution.context.entry(key).or_insert(value);
+                }
+            }
+        }
+
+        StepResult {
+            step_id: step.id.clone(),
+            status: if failures.is_empty() { StepStatus::Completed } else { StepStatus::Failed },
+            output: serde_json::json!({ "branches": branch_ids, "failed": failures }),
+            error: (!failures.is_empty()).then(|| format!("Branches failed: {}", failures.join(", "))),
+            duration_ms: start.elapsed().as_millis() as u64,
+            attempts: 1,
+        }
+    }
+
+    async fn execute_step(
+        &self,
+        step: &PlaybookStep,
+        context: &mut HashMap<String, serde_json::Value>,
+        remaining: std::time::Duration,
+    ) -> StepResult {
+        let start = std::time::Instant::now();
+
+        tracing::debug!("Executing step: {}", step.name);
+
+        // Check condition
+        if let Some(condition) = &step.condition {
+            if !self.evaluate_condition(condition, context) {
+                return StepResult {
+                    step_id: step.id.clone(),
+                    status: StepStatus::Skipped,
+                    output: serde_json::Value::Null,
+                    error: None,
+                    duration_ms: start.elapsed().as_millis() as u64,
+                    attempts: 0,
+                };
+            }
+        }
+
+        let timeout = std::time::Duration::from_secs(step.timeout_secs).min(remaining);
+        let mut attempts = 0;
+        let (status, error) = loop {
+            attempts += 1;
+            let (status, error) = match tokio::time::timeout(timeout, self.execute_action(&step.action, context)).await {
+                Ok(Ok(output)) => {
+                    return StepResult {
+                        step_id: step.id.clone(),
+                        status: StepStatus::Completed,
+                        output,
+                        error: None,
+                        duration_ms: start.elapsed().as_millis() as u64,
+                        attempts,
+                    };
+                }
+                Ok(Err(e)) => (StepStatus::Failed, e.to_string()),
+                Err(_) => (StepStatus::TimedOut, format!("Timed out after {}s", timeout.as_secs())),
+            };
+            if attempts > step.retries {
+                break (status, error);
+            }
+            tracing::debug!("Step {} attempt {} failed: {}", step.id, attempts, error);
+            tokio::time::sleep(std::time::Duration::from_secs(step.retry_delay_secs * attempts as u64)).await;
+        };
+
+        StepResult {
+            step_id: step.id.clone(),
+            status,
+            output: serde_json::Value::Null,
+            error: Some(error),
+            duration_ms: start.elapsed().as_millis() as u64,
+            attempts,
+        }
+    }
+
+    fn evaluate_condition(
+        &self,
+        condition: &StepCondition,
+        context: &HashMap<String, serde_json::Value>,
+    ) -> bool {
+        let value = lookup(context, &condition.field).map(value_text);
+
+        match condition.operator {
+            ConditionOperator::Exists => value.is_some(),
+            ConditionOperator::Equals => {
+                value.map(|v| v == condition.value).unwrap_or(false)
+            }
+            ConditionOperator::NotEquals => {
+                value.map(|v| v != condition.value).unwrap_or(true)
+            }
+            ConditionOperator::Contains => {
+                value.map(|v| v.contains(&condition.value)).unwrap_or(false)
+            }
+            ConditionOperator::GreaterThan => {
+                value.and_then(|v| compare(&v, &condition.value)) == Some(std::cmp::Ordering::Greater)
+            }
+            ConditionOperator::LessThan => {
+                value.and_then(|v| compare(&v, &condition.value)) == Some(std::cmp::Ordering::Less)
+            }
+        }
+    }
+
+    /// Evaluate a `field op value` expression, e.g. `alert.severity >= High`
+    fn evaluate_expression(&self, expression: &str, context: &HashMap<String, serde_json::Value>) -> Result<bool, ActionError> {
+        let mut parts = expression.splitn(3, ' ');
+        let field = parts.next().unwrap_or_default();
+        let op = parts.next().unwrap_or("exists");
+        let expected = parts.next().unwrap_or_default().trim_matches('"');
+        let value = lookup(context, field).map(value_text);
+
+        let ordering = value.as_deref().and_then(|v| compare(v, expected));
+        Ok(match op {
+            "exists" => value.is_some(),
+            "==" => value.as_deref() == Some(expected),
+            "!=" => value.as_deref() != Some(expected),
+            "contains" => value.is_some_and(|v| v.contains(expected)),
+            ">" => ordering == Some(std::cmp::Ordering::Greater),
+            ">=" => ordering.is_some_and(|o| o != std::cmp::Ordering::Less),
+            "<" => ordering == Some(std::cmp::Ordering::Less),
+            "<=" => ordering.is_some_and(|o| o != std::cmp::Ordering::Greater),
+            _ => return Err(ActionError::new(format!("Unknown operator in condition: {}", op))),
+        })
+    }
+
+    async fn execute_action(
+        &self,
+        action: &PlaybookAction,
+        context: &mut HashMap<String, serde_json::Value>,
+    ) -> Result<serde_json::Value, ActionError> {
+        let handler = self.actions.get(action.type_name()).map(|h| h.clone());
+        if let Some(handler) = handler {
+            return handler.execute(action, context).await;
+        }
+
+        let alert_id = lookup(context, "alert.id").map(value_text).unwrap_or_default();
+        let reason = format!("SOAR response to alert {}", alert_id);
+
+        match action {
+            PlaybookAction::Wait { seconds } => {
+                tokio::time::sleep(tokio::time::Duration::from_secs(*seconds)).await;
+                Ok(serde_json::json!({"waited": seconds}))
+            }
+            PlaybookAction::Conditional { condition, .. } => {
+                let matched = self.evaluate_expression(condition, context)?;
+                Ok(serde_json::json!({"branch": if matched { "then" } else { "else" }}))
+            }
+            PlaybookAction::BlockIp { ip_field, ttl_secs } => {
+                let ip = required(context, ip_field)?;
+                let blocklist = self.ip_blocklist.as_ref()
+                    .ok_or_else(|| ActionError::new("No IP blocklist configured"))?;
+                tracing::info!("SOAR: Blocking IP {}", ip);
+                let reference = blocklist.block_ip(&ip, &reason, ttl_secs.map(std::time::Duration::from_secs)).await?;
+                Ok(serde_json::json!({"blocked_ip": ip, "reference": reference}))
+            }
+            PlaybookAction::IsolateHost { host_field } => {
+                let host = context.get(host_field)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown");
+                tracing::info!("SOAR: Isolating host {}", host);
+                Ok(serde_json::json!({"isolated_host": host}))
+            }
+            PlaybookAction::DisableUser { user_field } => {
+                let user = required(context, user_field)?;
+                let access = self.user_access.as_ref()
+                    .ok_or_else(|| ActionError::new("No ZTNA user access control configured"))?;
+                tracing::info!("SOAR: Disabling user {}", user);
+                let sessions = access.disable_user(&user, &reason).await?;
+                Ok(serde_json::json!({"disabled_user": user, "sessions_terminated": sessions}))
+            }
+            PlaybookAction::QuarantineEmail { message_field } => {
+                let message_id = required(context, message_field)?;
+                let quarantine = self.mail_quarantine.as_ref()
+                    .ok_or_else(|| ActionError::new("No mail quarantine configured"))?;
+                tracing::info!("SOAR: Quarantining message {}", message_id);
+                let reference = quarantine.quarantine_message(&message_id, &reason).await?;
+                Ok(serde_json::json!({"quarantined_message": message_id, "reference": reference}))
+            }
+            PlaybookAction::SendSlack { channel, template } => {
+                tracing::info!("SOAR: Sending Slack to {} (template: {})", channel, template);
+                Ok(serde_json::json!({"sent_to": channel}))
+            }
+            PlaybookAction::SendEmail { recipients, template } => {
+                tracing::info!("SOAR: Sending email to {:?} (template: {})", recipients, template);
+                Ok(serde_json::json!({"sent_to": recipients}))
+            }
+            PlaybookAction::CreateCase { template } => {
+                let cases = self.case_sink.as_ref()
+                    .ok_or_else(|| ActionError::new("No case manager configured"))?;
+                let alert: SecurityAlert = context.get("alert")
+                    .and_then(|a| serde_json::from_value(a.clone()).ok())
+                    .ok_or_else(|| ActionError::new("No alert in execution context"))?;
+                tracing::info!("SOAR: Creating case from template {}", template);
+                let case_id = cases.open_case(&alert, template).await?;
+                context.insert("case_id".to_string(), serde_json::json!(case_id));
+                Ok(serde_json::json!({"case_id": case_id}))
+            }
+            PlaybookAction::CollectEvidence { evidence_types, lookback_secs } => {
+                let forensics = self.forensics.as_ref()
+                    .ok_or_else(|| ActionError::new("No forensics collector configured"))?;
+                let alert: SecurityAlert = context.get("alert")
+                    .and_then(|a| serde_json::from_value(a.clone()).ok())
+                    .ok_or_else(|| ActionError::new("No alert in execution context"))?;
+                let text = |field: &str| lookup(context, field).map(value_text).filter(|v| !v.is_empty());
+                let now = chrono::Utc::now();
+                let target = CollectionTarget {
+                    tenant_id: text("tenant_id"),
+                    host: text("source_host"),
+                    ip: text("source_ip"),
+                    user: text("user"),
+                    message_id: text("message_id"),
+                    session_id: text("event.raw_data.session_id"),
+                    alert_id: Some(alert.id.clone()),
+                    from: Some(now - chrono::Duration::seconds(*lookback_secs as i64)),
+                    to: Some(now),
+                    reason,
+                };
+                let case_id = text("case_id");
+                tracing::info!("SOAR: Collecting {:?} for alert {}", evidence_types, alert.id);
+                let request = CollectionRequest { evidence_types: evidence_types.clone(), target };
+                let evidence = forensics.collect_evidence(&alert, case_id.as_deref(), request).await?;
+                Ok(serde_json::json!({"evidence_ids": evidence, "collected": evidence.len()}))
+            }
+            _ => {
+                Ok(serde_json::json!({"status": "executed"}))
+            }
+        }
+    }
+
+    fn save(&self, execution: &PlaybookExecution) {
+        self.executions.insert(execution.id.clone(), execution.clone());
+    }
+
+    /// Get execution count
+    pub async fn get_execution_count(&self) -> u64 {
+        self.execution_count.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Get execution status
+    pub fn get_execution(&self, id: &str) -> Option<PlaybookExecution> {
+        self.executions.get(id).map(|e| e.clone())
+    }
+
+    /// Every execution started for an alert, oldest first
+    pub fn executions_for_alert(&self, alert_id: &str) -> Vec<PlaybookExecution> {
+        let ids = self.alert_executions.get(alert_id)
+            .map(|ids| ids.clone())
+            .unwrap_or_default();
+        ids.iter().filter_map(|id| self.get_execution(id)).collect()
+    }
+}
+
+impl Default for SoarEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Initial execution context: the alert plus flat fields playbooks commonly
+/// reference
+fn alert_context(alert: &SecurityAlert, event: Option<&SecurityEvent>) -> HashMap<String, serde_json::Value> {
+    let mut context = HashMap::new();
+    context.insert("alert".to_string(), serde_json::to_value(alert).unwrap_or_default());
+    context.insert("alert_id".to_string(), serde_json::json!(alert.id));
+    context.insert("alert_type".to_string(), serde_json::json!(alert.alert_type));
+    context.insert("severity".to_string(), serde_json::json!(alert.severity));
+    context.insert("risk_score".to_string(), serde_json::json!(alert.enrichment.risk_score));
+
+    let Some(event) = event else { return context };
+    context.insert("event".to_string(), serde_json::to_value(event).unwrap_or_default());
+    if let Some(tenant_id) = &event.tenant_id {
+        context.insert("tenant_id".to_string(), serde_json::json!(tenant_id));
+    }
+    if let Some(host) = &event.source.host {
+        context.insert("source_host".to_string(), serde_json::json!(host));
+    }
+    let indicator = |kind: IndicatorType| {
+        event.indicators.iter().find(|i| i.indicator_type == kind).map(|i| i.value.clone())
+    };
+    if let Some(ip) = event.source.ip.clone().or_else(|| indicator(IndicatorType::IpAddress)) {
+        context.insert("source_ip".to_string(), serde_json::json!(ip));
+    }
+    if let Some(user) = indicator(IndicatorType::Username).or_else(|| indicator(IndicatorType::Email)) {
+        context.insert("user".to_string(), serde_json::json!(user));
+    }
+    if let Some(message_id) = event.raw_data.get("message_id").and_then(|m| m.as_str()) {
+        context.insert("message_id".to_string(), serde_json::json!(message_id));
+    }
+    context
+}
+
+/// Make a step's output addressable as `steps.<id>`
+fn record_output(context: &mut HashMap<String, serde_json::Value>, result: &StepResult) {
+    let steps = context.entry("steps".to_string()).or_insert_with(|| serde_json::json!({}));
+    if let Some(steps) = steps.as_object_mut() {
+        steps.insert(result.step_id.clone(), result.output.clone());
+    }
+}
+
+/// Resolve a dotted path: the first segment is a context key, the rest walk
+/// into its JSON value
+fn lookup<'a>(context: &'a HashMap<String, serde_json::Value>, path: &str) -> Option<&'a serde_json::Value> {
+    if let Some(value) = context.get(path) {
+        return Some(value);
+    }
+    let mut parts = path.split('.');
+    let root = context.get(parts.next()?)?;
+    parts.try_fold(root, |value, key| match value {
+        serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => value.get(key),
+    })
+    .filter(|v| !v.is_null())
+}
+
+fn value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn required(context: &HashMap<String, serde_json::Value>, field: &str) -> Result<String, ActionError> {
+    lookup(context, field)
+        .map(value_text)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| ActionError::new(format!("Missing field: {}", field)))
+}
+
+/// Compare numerically, then by severity rank, then as text
+fn compare(actual: &str, expected: &str) -> Option<std::cmp::Ordering> {
+    if let (Ok(a), Ok(b)) = (actual.parse::<f64>(), expected.parse::<f64>()) {
+        return a.partial_cmp(&b);
+    }
+    let severity = |s: &str| serde_json::from_value::<Severity>(serde_json::json!(s)).ok();
+    if let (Some(a), Some(b)) = (severity(actual), severity(expected)) {
+        return Some(a.cmp(&b));
+    }
+    Some(actual.cmp(expected))
+}
+
+/// An approval whose execution record vanished; resume it as a bare record
+fn unreachable_execution(pending: &PendingApproval) -> PlaybookExecution {
+    PlaybookExecution {
+        id: pending.execution_id.clone(),
+        playbook_id: pending.playbook_id.clone(),
+        alert_id: pending.alert_id.clone(),
+        started_at: pending.requested_at,
+        ended_at: None,
+        status: ExecutionStatus::Running,
+        current_step: Some(pending.step_id.clone()),
+        step_results: HashMap::new(),
+        path: vec![pending.step_id.clone()],
+        context: HashMap::new(),
+    }
+}
+
+#[derive(Debug)]
+pub struct ActionError(String);
+
+impl ActionError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl std::fmt::Display for ActionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertEnrichment, AlertStatus};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// `run_script` handler that fails its first `failures` calls
+    struct Flaky {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl ActionHandler for Flaky {
+        fn action_type(&self) -> &str {
+            "run_script"
+        }
+
+        async fn execute(
+            &self,
+            _action: &PlaybookAction,
+            _context: &mut HashMap<String, serde_json::Value>,
+        ) -> Result<serde_json::Value, ActionError> {
+            let call = self.calls.fetch_add(1, Ordering::Relaxed);
+            if call < self.failures {
+                return Err(ActionError::new("script failed"));
+            }
+            Ok(serde_json::json!({"exit_code": 0}))
+        }
+    }
+
+    fn engine(yaml: &str, failures: u32) -> SoarEngine {
+        let engine = SoarEngine::new();
+        engine.load_playbook_yaml(yaml).unwrap();
+        engine.register_action(Arc::new(Flaky { failures, calls: AtomicU32::new(0) }));
+        engine
+    }
+
+    fn alert(alert_type: &str, severity: Severity, risk_score: f64) -> SecurityAlert {
+        SecurityAlert {
+            id: uuid::Uuid::new_v4().to_string(),
+            events: vec![],
+            alert_type: alert_type.to_string(),
+            severity,
+            status: AlertStatus::New,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            assigned_to: None,
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            enrichment: AlertEnrichment { risk_score, ..Default::default() },
+            case_id: None,
+        }
+    }
+
+    const GATED: &str = r##"
+id: gated
+name: Gated
+triggers:
+  - alert_type: TestAlert
+conditions:
+  - { field: risk_score, operator: greater_than, value: "50" }
+steps:
+  - id: script
+    action: { type: run_script, script: contain }
+    retries: 2
+    retry_delay_secs: 0
+    on_success: approve
+    on_failure: notify
+  - id: approve
+    action: { type: approval, approvers: [soc-lead] }
+    on_success: check
+    on_failure: notify
+  - id: check
+    action: { type: conditional, condition: "alert.severity >= High", then_step: page, else_step: notify }
+  - id: page
+    action: { type: page_on_call, team: soc }
+    on_success: notify
+  - id: notify
+    action: { type: send_slack, channel: "#soc", template: alert }
+"##;
+
+    #[tokio::test]
+    async fn test_retry_then_approval_gate() {
+        let engine = engine(GATED, 2);
+
+        assert!(engine.trigger(&alert("TestAlert", Severity::Critical, 10.0)).await.is_empty());
+
+        let started = engine.trigger(&alert("TestAlert", Severity::Critical, 80.0)).await;
+        assert_eq!(started.len(), 1);
+        let execution = engine.get_execution(&started[0]).unwrap();
+        assert_eq!(execution.status, ExecutionStatus::AwaitingApproval);
+        assert_eq!(execution.path, vec!["script", "approve"]);
+        assert_eq!(execution.step_results["script"].attempts, 3);
+        assert_eq!(execution.step_results["script"].status, StepStatus::Completed);
+        assert_eq!(engine.pending_approvals().len(), 1);
+
+        assert!(engine.decide_approval(&execution.id, "mallory", true, None).await.is_err());
+        assert_eq!(engine.pending_approvals().len(), 1);
+
+        let execution = engine.decide_approval(&execution.id, "soc-lead", true, Some("go")).await.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Completed);
+        assert_eq!(execution.path, vec!["script", "approve", "check", "page", "notify"]);
+        assert_eq!(execution.context["steps"]["approve"]["approver"], "soc-lead");
+        assert!(engine.pending_approvals().is_empty());
+        assert!(engine.decide_approval(&execution.id, "soc-lead", true, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reject_expire_and_cancel() {
+        let engine = engine(r##"
+id: gate
+name: Gate
+triggers:
+  - severity: High
+steps:
+  - id: approve
+    action: { type: approval, timeout_secs: 0 }
+    on_success: page
+    on_failure: notify
+  - id: page
+    action: { type: page_on_call, team: soc }
+  - id: notify
+    action: { type: send_slack, channel: "#soc", template: alert }
+"##, 0);
+
+        assert!(engine.trigger(&alert("Other", Severity::Medium, 0.0)).await.is_empty());
+        let rejected = engine.trigger(&alert("Other", Severity::High, 0.0)).await.remove(0);
+        let expired = engine.trigger(&alert("Other", Severity::Critical, 0.0)).await.remove(0);
+        let cancelled = engine.trigger(&alert("Other", Severity::High, 0.0)).await.remove(0);
+
+        let execution = engine.decide_approval(&rejected, "anyone", false, None).await.unwrap();
+        assert_eq!(execution.path, vec!["approve", "notify"]);
+        assert_eq!(execution.step_results["approve"].status, StepStatus::Failed);
+        assert_eq!(execution.step_results["approve"].error.as_deref(), Some("Rejected by anyone"));
+        assert_eq!(execution.status, ExecutionStatus::Completed);
+
+        assert!(engine.cancel(&cancelled));
+        assert!(!engine.cancel(&cancelled));
+        assert_eq!(engine.get_execution(&cancelled).unwrap().status, ExecutionStatus::Cancelled);
+
+        let resumed = engine.expire_approvals().await;
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].id, expired);
+        assert_eq!(resumed[0].path, vec!["approve", "notify"]);
+        assert_eq!(resumed[0].step_results["approve"].status, StepStatus::TimedOut);
+        assert!(engine.pending_approvals().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conditional_branch_and_exhausted_retries() {
+        let engine = engine(r##"
+id: branchy
+name: Branchy
+triggers:
+  - alert_type: Branchy
+steps:
+  - id: check
+    action: { type: conditional, condition: "alert.severity >= High", then_step: page, else_step: script }
+  - id: page
+    action: { type: page_on_call, team: soc }
+  - id: script
+    action: { type: run_script, script: contain }
+    retries: 1
+    retry_delay_secs: 0
+"##, u32::MAX);
+
+        let high = engine.run_manual("branchy", &alert("Branchy", Severity::High, 0.0)).await.unwrap();
+        assert_eq!(high.path, vec!["check", "page"]);
+        assert_eq!(high.status, ExecutionStatus::Completed);
+
+        let low = engine.run_manual("branchy", &alert("Branchy", Severity::Low, 0.0)).await.unwrap();
+        assert_eq!(low.path, vec!["check", "script"]);
+        assert_eq!(low.step_results["script"].attempts, 2);
+        assert_eq!(low.step_results["script"].status, StepStatus::Failed);
+        assert_eq!(low.status, ExecutionStatus::Failed);
+
+        assert!(engine.run_manual("missing", &alert("Branchy", Severity::Low, 0.0)).await.is_none());
+    }
+}
@@ -0,0 +1,76 @@
+//! Response backends for built-in playbook actions
+//!
+//! The SOC crate sits below threat-intel, ZTNA and the email gateway in the
+//! dependency graph, so those services implement these traits and are handed
+//! to [`SoarEngine`](super::SoarEngine) at startup.
+
+use super::ActionError;
+use crate::cases::CaseManager;
+use crate::forensics::{CollectionRequest, ForensicsCollector};
+use crate::SecurityAlert;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Pushes IP blocks to enforcement points (threat-intel distribution)
+#[async_trait]
+pub trait IpBlocklist: Send + Sync {
+    /// Block an address; returns a reference to the created block
+    async fn block_ip(&self, ip: &str, reason: &str, ttl: Option<Duration>) -> Result<String, ActionError>;
+}
+
+/// Revokes a user's access (ZTNA)
+#[async_trait]
+pub trait UserAccessControl: Send + Sync {
+    /// Disable a user; returns the number of sessions terminated
+    async fn disable_user(&self, user: &str, reason: &str) -> Result<usize, ActionError>;
+}
+
+/// Pulls delivered messages into quarantine (email gateway)
+#[async_trait]
+pub trait MailQuarantine: Send + Sync {
+    /// Quarantine a message by ID; returns the quarantine reference
+    async fn quarantine_message(&self, message_id: &str, reason: &str) -> Result<String, ActionError>;
+}
+
+/// Opens investigation cases
+#[async_trait]
+pub trait CaseSink: Send + Sync {
+    /// Open a case for an alert; returns the case ID
+    async fn open_case(&self, alert: &SecurityAlert, template: &str) -> Result<String, ActionError>;
+}
+
+#[async_trait]
+impl CaseSink for CaseManager {
+    async fn open_case(&self, alert: &SecurityAlert, template: &str) -> Result<String, ActionError> {
+        Ok(self.create_from_alert(alert, Some(template)).await.id)
+    }
+}
+
+/// Collects forensic artifacts
+#[async_trait]
+pub trait ForensicsSink: Send + Sync {
+    /// Collect evidence for an alert, filed under a case when there is one;
+    /// returns the evidence IDs
+    async fn collect_evidence(
+        &self,
+        alert: &SecurityAlert,
+        case_id: Option<&str>,
+        request: CollectionRequest,
+    ) -> Result<Vec<String>, ActionError>;
+}
+
+#[async_trait]
+impl ForensicsSink for ForensicsCollector {
+    async fn collect_evidence(
+        &self,
+        alert: &SecurityAlert,
+        case_id: Option<&str>,
+        request: CollectionRequest,
+    ) -> Result<Vec<String>, ActionError> {
+        let case_id = case_id.or(alert.case_id.as_deref()).unwrap_or(alert.id.as_str());
+        let name = format!("SOAR collection for alert {}", alert.id);
+        let (_, evidence) = self.collect_for_case(case_id, &name, &request, "soar").await
+            .map_err(|e| ActionError::new(e.to_string()))?;
+        Ok(evidence.into_iter().map(|e| e.id).collect())
+    }
+}
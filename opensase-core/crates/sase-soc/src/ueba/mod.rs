@@ -0,0 +1,699 @@
+//! Entity Analytics (UEBA)
+//!
+//! Rolling risk scores per user and host. Every alert naming an entity,
+//! every UBA model output (from the ml/inference detectors) and every
+//! threat intel hit adds points to the entity's score; points decay with a
+//! configurable half-life, so the score reflects recent behaviour. Scores
+//! are kept as a timeline for investigation.
+//!
+//! Entities whose score crosses the watch thresholds go on the
+//! [`watchlist`], and the change is pushed to registered sinks so access
+//! decisions tighten (ZTNA requires MFA and re-scores live sessions).
+
+pub mod watchlist;
+
+pub use watchlist::{
+    WatchLevel, WatchlistChange, WatchlistEntry, WatchlistPolicy, WatchlistSink, WebhookWatchlistSink,
+};
+
+use crate::{AlertStatus, SecurityAlert, Severity, ThreatIntelMatch};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    User,
+    Host,
+}
+
+/// A user (by user id) or a host (by hostname or asset id)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EntityId {
+    pub kind: EntityKind,
+    pub id: String,
+}
+
+impl EntityId {
+    pub fn user(id: &str) -> Self {
+        Self { kind: EntityKind::User, id: id.to_string() }
+    }
+
+    pub fn host(id: &str) -> Self {
+        Self { kind: EntityKind::Host, id: id.to_string() }
+    }
+}
+
+impl std::fmt::Display for EntityId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            EntityKind::User => write!(f, "user:{}", self.id),
+            EntityKind::Host => write!(f, "host:{}", self.id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskSource {
+    Alert,
+    Model,
+    ThreatIntel,
+}
+
+/// Points added to an entity's score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskContribution {
+    pub source: RiskSource,
+    /// Points at `at`, before decay
+    pub points: f64,
+    pub at: DateTime<Utc>,
+    pub reason: String,
+    /// Alert id, model name or intel feed
+    pub reference: Option<String>,
+}
+
+/// Output of a UBA model for one entity, e.g. an ml/inference
+/// `UbaPrediction`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelOutput {
+    pub model: String,
+    /// Model risk, 0.0-1.0
+    pub risk_score: f64,
+    pub anomalies: Vec<String>,
+    pub explanation: String,
+    pub observed_at: DateTime<Utc>,
+}
+
+/// How contributions are weighted and how fast they fade
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringConfig {
+    /// Hours for a contribution to lose half its points
+    pub half_life_hours: f64,
+    pub info_points: f64,
+    pub low_points: f64,
+    pub medium_points: f64,
+    pub high_points: f64,
+    pub critical_points: f64,
+    /// Points for a model risk of 1.0
+    pub model_points: f64,
+    /// Model outputs below this risk are ignored
+    pub model_min_risk: f64,
+    /// Points for an intel hit at confidence 1.0
+    pub intel_points: f64,
+    /// Contributions older than this are dropped
+    pub retention_days: i64,
+    /// Timeline points kept per entity
+    pub max_timeline: usize,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            half_life_hours: 24.0,
+            info_points: 1.0,
+            low_points: 5.0,
+            medium_points: 10.0,
+            high_points: 20.0,
+            critical_points: 35.0,
+            model_points: 40.0,
+            model_min_risk: 0.3,
+            intel_points: 30.0,
+            retention_days: 30,
+            max_timeline: 500,
+        }
+    }
+}
+
+impl ScoringConfig {
+    fn severity_points(&self, severity: Severity) -> f64 {
+        match severity {
+            Severity::Info => self.info_points,
+            Severity::Low => self.low_points,
+            Severity::Medium => self.medium_points,
+            Severity::High => self.high_points,
+            Severity::Critical => self.critical_points,
+        }
+    }
+
+    fn decayed(&self, contribution: &RiskContribution, now: DateTime<Utc>) -> f64 {
+        let age_hours = (now - contribution.at).num_seconds().max(0) as f64 / 3600.0;
+        contribution.points * 0.5f64.powf(age_hours / self.half_life_hours.max(0.01))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScorePoint {
+    pub at: DateTime<Utc>,
+    pub score: f64,
+}
+
+struct EntityState {
+    contributions: VecDeque<RiskContribution>,
+    timeline: VecDeque<ScorePoint>,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    peak: f64,
+}
+
+/// Ranking row for [`EntityAnalytics::top_entities`]
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityScore {
+    pub entity: EntityId,
+    pub score: f64,
+    pub peak: f64,
+    pub last_seen: DateTime<Utc>,
+    pub watch: Option<WatchLevel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityProfile {
+    pub entity: EntityId,
+    pub score: f64,
+    pub peak: f64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Contributions still counted, newest last
+    pub contributions: Vec<RiskContribution>,
+    pub watch: Option<WatchlistEntry>,
+}
+
+pub struct EntityAnalytics {
+    entities: dashmap::DashMap<EntityId, EntityState>,
+    watchlist: dashmap::DashMap<EntityId, WatchlistEntry>,
+    sinks: parking_lot::RwLock<Vec<Arc<dyn WatchlistSink>>>,
+    config: ScoringConfig,
+    policy: WatchlistPolicy,
+}
+
+impl EntityAnalytics {
+    pub fn new() -> Self {
+        Self {
+            entities: dashmap::DashMap::new(),
+            watchlist: dashmap::DashMap::new(),
+            sinks: parking_lot::RwLock::new(Vec::new()),
+            config: ScoringConfig::default(),
+            policy: WatchlistPolicy::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: ScoringConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: WatchlistPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Send watchlist changes to `sink`
+    pub fn add_sink(&self, sink: Arc<dyn WatchlistSink>) {
+        self.sinks.write().push(sink);
+    }
+
+    // =========================================================================
+    // Inputs
+    // =========================================================================
+
+    /// Score the user and host an alert names, including the alert's
+    /// threat intel matches. False positives don't count.
+    pub async fn record_alert(&self, alert: &SecurityAlert) -> Vec<WatchlistChange> {
+        if alert.status == AlertStatus::FalsePositive {
+            return Vec::new();
+        }
+        let mut entities = Vec::new();
+        if let Some(user) = &alert.enrichment.user_info {
+            entities.push(EntityId::user(&user.user_id));
+        }
+        if let Some(asset) = &alert.enrichment.asset_info {
+            entities.push(EntityId::host(asset.hostname.as_deref().unwrap_or(&asset.asset_id)));
+        }
+
+        let mut changes = Vec::new();
+        for entity in entities {
+            let mut contributions = vec![RiskContribution {
+                source: RiskSource::Alert,
+                points: self.config.severity_points(alert.severity),
+                at: alert.created_at,
+                reason: format!("{:?} alert: {}", alert.severity, alert.alert_type),
+                reference: Some(alert.id.clone()),
+            }];
+            contributions.extend(alert.enrichment.threat_intel.iter().map(|hit| self.intel_contribution(hit, alert.created_at)));
+            changes.extend(self.contribute(&entity, contributions).await);
+        }
+        changes
+    }
+
+    /// Score a UBA model's output for an entity
+    pub async fn record_model_output(&self, entity: &EntityId, output: &ModelOutput) -> Vec<WatchlistChange> {
+        let risk = output.risk_score.clamp(0.0, 1.0);
+        if risk < self.config.model_min_risk {
+            self.touch(entity, output.observed_at);
+            return Vec::new();
+        }
+        let mut reason = format!("{} risk {:.2}", output.model, risk);
+        if !output.anomalies.is_empty() {
+            reason.push_str(&format!(" ({})", output.anomalies.join(", ")));
+        }
+        self.contribute(entity, vec![RiskContribution {
+            source: RiskSource::Model,
+            points: risk * self.config.model_points,
+            at: output.observed_at,
+            reason,
+            reference: Some(output.model.clone()),
+        }]).await
+    }
+
+    /// Score a threat intel hit attributed to an entity, e.g. a host
+    /// contacting a known C2 address
+    pub async fn record_intel_hit(&self, entity: &EntityId, hit: &ThreatIntelMatch) -> Vec<WatchlistChange> {
+        self.contribute(entity, vec![self.intel_contribution(hit, Utc::now())]).await
+    }
+
+    fn intel_contribution(&self, hit: &ThreatIntelMatch, at: DateTime<Utc>) -> RiskContribution {
+        RiskContribution {
+            source: RiskSource::ThreatIntel,
+            points: hit.confidence.clamp(0.0, 1.0) * self.config.intel_points,
+            at,
+            reason: format!("{} indicator {}", hit.threat_type, hit.indicator),
+            reference: Some(hit.feed.clone()),
+        }
+    }
+
+    fn touch(&self, entity: &EntityId, at: DateTime<Utc>) {
+        if let Some(mut state) = self.entities.get_mut(entity) {
+            state.last_seen = state.last_seen.max(at);
+        }
+    }
+
+    async fn contribute(&self, entity: &EntityId, contributions: Vec<RiskContribution>) -> Vec<WatchlistChange> {
+        let now = Utc::now();
+        let reason = contributions.iter().map(|c| c.reason.as_str()).collect::<Vec<_>>().join("; ");
+        let score = {
+            let mut state = self.entities.entry(entity.clone()).or_insert_with(|| EntityState {
+                contributions: VecDeque::new(),
+                timeline: VecDeque::new(),
+                first_seen: now,
+                last_seen: now,
+                peak: 0.0,
+            });
+            for contribution in contributions {
+                state.last_seen = state.last_seen.max(contribution.at);
+                state.contributions.push_back(contribution);
+            }
+            self.update_score(&mut state, now)
+        };
+
+        let change = self.evaluate_watch(entity, score, &reason, now);
+        self.dispatch(change.into_iter().collect()).await
+    }
+
+    /// Prune old contributions and record the current score
+    fn update_score(&self, state: &mut EntityState, now: DateTime<Utc>) -> f64 {
+        let cutoff = now - Duration::days(self.config.retention_days);
+        state.contributions.retain(|c| c.at >= cutoff);
+
+        let score = self.current_score(state, now);
+        state.peak = state.peak.max(score);
+        state.timeline.push_back(ScorePoint { at: now, score });
+        while state.timeline.len() > self.config.max_timeline {
+            state.timeline.pop_front();
+        }
+        score
+    }
+
+    fn current_score(&self, state: &EntityState, now: DateTime<Utc>) -> f64 {
+        let total: f64 = state.contributions.iter().map(|c| self.config.decayed(c, now)).sum();
+        total.min(100.0)
+    }
+
+    // =========================================================================
+    // Queries
+    // =========================================================================
+
+    /// Current score, 0-100
+    pub fn score(&self, entity: &EntityId) -> f64 {
+        self.entities.get(entity)
+            .map(|state| self.current_score(&state, Utc::now()))
+            .unwrap_or(0.0)
+    }
+
+    /// Highest-scoring entities, optionally of one kind
+    pub fn top_entities(&self, kind: Option<EntityKind>, limit: usize) -> Vec<EntityScore> {
+        let now = Utc::now();
+        let mut scores: Vec<EntityScore> = self.entities.iter()
+            .filter(|e| kind.is_none_or(|k| e.key().kind == k))
+            .map(|e| EntityScore {
+                entity: e.key().clone(),
+                score: self.current_score(&e, now),
+                peak: e.peak,
+                last_seen: e.last_seen,
+                watch: self.watchlist.get(e.key()).map(|w| w.level),
+            })
+            .filter(|s| s.score > 0.0)
+            .collect();
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.entity.cmp(&b.entity)));
+        scores.truncate(limit);
+        scores
+    }
+
+    /// Recorded scores, oldest first
+    pub fn timeline(&self, entity: &EntityId, since: Option<DateTime<Utc>>) -> Vec<ScorePoint> {
+        self.entities.get(entity)
+            .map(|state| state.timeline.iter()
+                .filter(|p| since.is_none_or(|since| p.at >= since))
+                .copied()
+                .collect())
+            .unwrap_or_default()
+    }
+
+    pub fn profile(&self, entity: &EntityId) -> Option<EntityProfile> {
+        let state = self.entities.get(entity)?;
+        Some(EntityProfile {
+            entity: entity.clone(),
+            score: self.current_score(&state, Utc::now()),
+            peak: state.peak,
+            first_seen: state.first_seen,
+            last_seen: state.last_seen,
+            contributions: state.contributions.iter().cloned().collect(),
+            watch: self.watchlist.get(entity).map(|w| w.clone()),
+        })
+    }
+
+    // =========================================================================
+    // Watchlist
+    // =========================================================================
+
+    pub fn watchlist(&self) -> Vec<WatchlistEntry> {
+        let mut entries: Vec<WatchlistEntry> = self.watchlist.iter().map(|e| e.clone()).collect();
+        entries.sort_by(|a, b| b.level.cmp(&a.level).then_with(|| a.entity.cmp(&b.entity)));
+        entries
+    }
+
+    pub fn watch_entry(&self, entity: &EntityId) -> Option<WatchlistEntry> {
+        self.watchlist.get(entity).map(|e| e.clone())
+    }
+
+    /// Put an entity on the watchlist by hand. Manual entries can be
+    /// raised automatically but are only lowered or removed by hand.
+    pub async fn watch(&self, entity: &EntityId, level: WatchLevel, reason: &str, by: &str) -> WatchlistChange {
+        let now = Utc::now();
+        let entry = WatchlistEntry {
+            entity: entity.clone(),
+            level,
+            score: self.score(entity),
+            reason: reason.to_string(),
+            added_by: by.to_string(),
+            added_at: now,
+            updated_at: now,
+            hold_until: now,
+            manual: true,
+        };
+        let change = match self.watchlist.insert(entity.clone(), entry.clone()) {
+            Some(previous) => WatchlistChange::Changed { entry, previous: previous.level },
+            None => WatchlistChange::Added { entry },
+        };
+        self.dispatch(vec![change.clone()]).await;
+        change
+    }
+
+    pub async fn unwatch(&self, entity: &EntityId, by: &str) -> Option<WatchlistChange> {
+        self.watchlist.remove(entity)?;
+        let change = WatchlistChange::Removed { entity: entity.clone(), reason: format!("removed by {}", by) };
+        self.dispatch(vec![change.clone()]).await;
+        Some(change)
+    }
+
+    fn evaluate_watch(&self, entity: &EntityId, score: f64, reason: &str, now: DateTime<Utc>) -> Option<WatchlistChange> {
+        let level = self.policy.level_for(score);
+        let hold = Duration::hours(self.policy.hold_hours);
+
+        let Some(mut entry) = self.watchlist.get_mut(entity) else {
+            let entry = WatchlistEntry::automatic(entity.clone(), level?, score, reason.to_string(), hold, now);
+            self.watchlist.insert(entity.clone(), entry.clone());
+            return Some(WatchlistChange::Added { entry });
+        };
+
+        let previous = entry.level;
+        if level.is_some_and(|level| level > previous) {
+            entry.level = level?;
+            entry.score = score;
+            entry.reason = reason.to_string();
+            entry.updated_at = now;
+            entry.hold_until = now + hold;
+            return Some(WatchlistChange::Changed { entry: entry.clone(), previous });
+        }
+        if entry.manual || now < entry.hold_until {
+            return None;
+        }
+        if score < self.policy.release_below {
+            drop(entry);
+            self.watchlist.remove(entity);
+            return Some(WatchlistChange::Removed {
+                entity: entity.clone(),
+                reason: format!("score fell to {:.1}", score),
+            });
+        }
+        if previous == WatchLevel::Critical && level == Some(WatchLevel::Elevated) {
+            entry.level = WatchLevel::Elevated;
+            entry.score = score;
+            entry.updated_at = now;
+            entry.hold_until = now + hold;
+            return Some(WatchlistChange::Changed { entry: entry.clone(), previous });
+        }
+        None
+    }
+
+    async fn dispatch(&self, changes: Vec<WatchlistChange>) -> Vec<WatchlistChange> {
+        if changes.is_empty() {
+            return changes;
+        }
+        let sinks: Vec<Arc<dyn WatchlistSink>> = self.sinks.read().clone();
+        for change in &changes {
+            tracing::info!("Watchlist change for {}: {:?}", change.entity(), change);
+            for sink in &sinks {
+                if let Err(e) = sink.apply(change).await {
+                    tracing::warn!("Watchlist sink {} failed for {}: {}", sink.name(), change.entity(), e);
+                }
+            }
+        }
+        changes
+    }
+
+    /// Re-score every entity as points decay, releasing or lowering
+    /// watchlist entries past their hold. Entities with nothing left to
+    /// count and no watchlist entry are forgotten.
+    pub async fn refresh(&self, now: DateTime<Utc>) -> Vec<WatchlistChange> {
+        let entities: Vec<EntityId> = self.entities.iter().map(|e| e.key().clone()).collect();
+
+        let mut changes = Vec::new();
+        for entity in entities {
+            let Some(score) = self.entities.get_mut(&entity).map(|mut state| self.update_score(&mut state, now)) else {
+                continue;
+            };
+            if let Some(change) = self.evaluate_watch(&entity, score, "scheduled re-score", now) {
+                changes.push(change);
+            }
+            let idle = self.entities.get(&entity).is_some_and(|s| s.contributions.is_empty());
+            if idle && !self.watchlist.contains_key(&entity) {
+                self.entities.remove(&entity);
+            }
+        }
+        self.dispatch(changes).await
+    }
+
+    /// Run [`Self::refresh`] every `interval`
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                self.refresh(Utc::now()).await;
+            }
+        })
+    }
+}
+
+impl Default for EntityAnalytics {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertEnrichment, AssetInfo, UserInfo};
+
+    fn alert(severity: Severity, user: &str, host: Option<&str>) -> SecurityAlert {
+        SecurityAlert {
+            id: uuid::Uuid::new_v4().to_string(),
+            events: vec![],
+            alert_type: "BruteForce".to_string(),
+            severity,
+            status: AlertStatus::New,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            assigned_to: None,
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            enrichment: AlertEnrichment {
+                user_info: Some(UserInfo {
+                    user_id: user.to_string(),
+                    username: user.to_string(),
+                    email: None,
+                    department: None,
+                    risk_level: "normal".to_string(),
+                }),
+                asset_info: host.map(|host| AssetInfo {
+                    asset_id: format!("asset-{}", host),
+                    asset_type: "server".to_string(),
+                    hostname: Some(host.to_string()),
+                    owner: None,
+                    criticality: "high".to_string(),
+                }),
+                ..Default::default()
+            },
+            case_id: None,
+        }
+    }
+
+    fn model(risk_score: f64, hours_ago: i64) -> ModelOutput {
+        ModelOutput {
+            model: "uba-login".to_string(),
+            risk_score,
+            anomalies: vec!["new_country".to_string()],
+            explanation: String::new(),
+            observed_at: Utc::now() - Duration::hours(hours_ago),
+        }
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.1, "{} != {}", actual, expected);
+    }
+
+    #[derive(Default)]
+    struct Recorder(parking_lot::Mutex<Vec<WatchlistChange>>);
+
+    #[async_trait::async_trait]
+    impl WatchlistSink for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        async fn apply(&self, change: &WatchlistChange) -> Result<(), String> {
+            self.0.lock().push(change.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alerts_score_user_host_and_intel() {
+        let analytics = EntityAnalytics::new();
+        let mut critical = alert(Severity::Critical, "alice", Some("web-1"));
+        critical.enrichment.threat_intel.push(ThreatIntelMatch {
+            feed: "abuse.ch".to_string(),
+            indicator: "203.0.113.7".to_string(),
+            threat_type: "c2".to_string(),
+            confidence: 0.5,
+        });
+        analytics.record_alert(&critical).await;
+
+        // 35 for the critical alert, 15 for the half-confidence intel hit
+        assert_near(analytics.score(&EntityId::user("alice")), 50.0);
+        assert_near(analytics.score(&EntityId::host("web-1")), 50.0);
+        let profile = analytics.profile(&EntityId::user("alice")).unwrap();
+        assert_eq!(profile.contributions.len(), 2);
+        assert_eq!(profile.contributions[1].source, RiskSource::ThreatIntel);
+
+        let mut dismissed = alert(Severity::Critical, "bob", None);
+        dismissed.status = AlertStatus::FalsePositive;
+        analytics.record_alert(&dismissed).await;
+        assert!(analytics.profile(&EntityId::user("bob")).is_none());
+
+        analytics.record_alert(&alert(Severity::Low, "bob", None)).await;
+        let users = analytics.top_entities(Some(EntityKind::User), 10);
+        assert_eq!(users.iter().map(|u| u.entity.id.as_str()).collect::<Vec<_>>(), vec!["alice", "bob"]);
+        assert_eq!(analytics.top_entities(Some(EntityKind::Host), 10)[0].entity, EntityId::host("web-1"));
+    }
+
+    #[tokio::test]
+    async fn test_model_outputs_decay_and_threshold() {
+        let analytics = EntityAnalytics::new();
+        let alice = EntityId::user("alice");
+
+        assert!(analytics.record_model_output(&alice, &model(0.2, 0)).await.is_empty());
+        assert!(analytics.profile(&alice).is_none());
+
+        // 40 points a half-life ago
+        analytics.record_model_output(&alice, &model(1.0, 24)).await;
+        assert_near(analytics.score(&alice), 20.0);
+        let profile = analytics.profile(&alice).unwrap();
+        assert_eq!(profile.contributions[0].reason, "uba-login risk 1.00 (new_country)");
+        assert_eq!(analytics.timeline(&alice, None).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_watchlist_raise_hold_lower_and_release() {
+        let analytics = EntityAnalytics::new()
+            .with_policy(WatchlistPolicy { hold_hours: 1, ..Default::default() });
+        let recorder = Arc::new(Recorder::default());
+        analytics.add_sink(recorder.clone());
+        let alice = EntityId::user("alice");
+
+        analytics.record_alert(&alert(Severity::Critical, "alice", None)).await;
+        let added = analytics.record_alert(&alert(Severity::Critical, "alice", None)).await;
+        assert!(matches!(&added[..], [WatchlistChange::Added { entry }] if entry.level == WatchLevel::Elevated));
+
+        let raised = analytics.record_alert(&alert(Severity::Critical, "alice", None)).await;
+        assert!(matches!(&raised[..], [WatchlistChange::Changed { previous: WatchLevel::Elevated, entry }]
+            if entry.level == WatchLevel::Critical));
+
+        // Still held, though the score has already dropped below critical
+        let now = Utc::now();
+        assert!(analytics.refresh(now + Duration::minutes(30)).await.is_empty());
+        // 105 points at half of a half-life is about 74
+        let lowered = analytics.refresh(now + Duration::hours(12)).await;
+        assert!(matches!(&lowered[..], [WatchlistChange::Changed { previous: WatchLevel::Critical, entry }]
+            if entry.level == WatchLevel::Elevated));
+
+        let released = analytics.refresh(now + Duration::days(3)).await;
+        assert!(matches!(&released[..], [WatchlistChange::Removed { .. }]));
+        assert!(analytics.watch_entry(&alice).is_none());
+        assert_eq!(recorder.0.lock().len(), 4);
+
+        // Forgotten once nothing counts any more
+        analytics.refresh(now + Duration::days(31)).await;
+        assert!(analytics.profile(&alice).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_manual_entries_are_only_removed_by_hand() {
+        let analytics = EntityAnalytics::new();
+        let host = EntityId::host("web-1");
+
+        let change = analytics.watch(&host, WatchLevel::Elevated, "under investigation", "analyst").await;
+        assert!(matches!(change, WatchlistChange::Added { .. }));
+        assert!(analytics.refresh(Utc::now() + Duration::days(5)).await.is_empty());
+
+        // Automatic scoring can still raise it
+        for _ in 0..3 {
+            analytics.record_alert(&alert(Severity::Critical, "alice", Some("web-1"))).await;
+        }
+        let entry = analytics.watch_entry(&host).unwrap();
+        assert_eq!(entry.level, WatchLevel::Critical);
+        assert!(entry.manual);
+
+        // Alice's automatic entry is released, the host's manual one stays
+        let released = analytics.refresh(Utc::now() + Duration::days(5)).await;
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].entity(), &EntityId::user("alice"));
+        assert_eq!(analytics.watchlist().len(), 1);
+
+        assert!(matches!(analytics.unwatch(&host, "analyst").await, Some(WatchlistChange::Removed { .. })));
+        assert!(analytics.unwatch(&host, "analyst").await.is_none());
+        assert!(analytics.watchlist().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+//! Entity watchlist
+//!
+//! Entities whose risk crosses the watch thresholds are put on the
+//! watchlist and stay there for at least the policy's hold time, so a
+//! score that dips for an hour doesn't flap enforcement. Changes go out to
+//! [`WatchlistSink`]s; [`WebhookWatchlistSink`] posts them to the ZTNA
+//! gateway's watchlist endpoint.
+
+use super::EntityId;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration as StdDuration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchLevel {
+    /// Tighter access: MFA on every request, lower risk headroom
+    Elevated,
+    /// Step-up or revocation of live sessions
+    Critical,
+}
+
+/// When entities go on and come off the watchlist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistPolicy {
+    /// Score (0-100) at which an entity is watched
+    pub elevated_at: f64,
+    /// Score at which a watched entity is escalated to critical
+    pub critical_at: f64,
+    /// Automatic entries are released once the score falls below this
+    pub release_below: f64,
+    /// Minimum time an automatic entry is held before release
+    pub hold_hours: i64,
+}
+
+impl Default for WatchlistPolicy {
+    fn default() -> Self {
+        Self {
+            elevated_at: 60.0,
+            critical_at: 85.0,
+            release_below: 40.0,
+            hold_hours: 24,
+        }
+    }
+}
+
+impl WatchlistPolicy {
+    pub fn level_for(&self, score: f64) -> Option<WatchLevel> {
+        if score >= self.critical_at {
+            Some(WatchLevel::Critical)
+        } else if score >= self.elevated_at {
+            Some(WatchLevel::Elevated)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistEntry {
+    pub entity: EntityId,
+    pub level: WatchLevel,
+    /// Score when the entry was last raised
+    pub score: f64,
+    pub reason: String,
+    /// `system` for automatic entries, otherwise the analyst
+    pub added_by: String,
+    pub added_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Earliest automatic release
+    pub hold_until: DateTime<Utc>,
+    /// Manual entries are only removed manually
+    pub manual: bool,
+}
+
+impl WatchlistEntry {
+    pub(super) fn automatic(entity: EntityId, level: WatchLevel, score: f64, reason: String, hold: Duration, now: DateTime<Utc>) -> Self {
+        Self {
+            entity,
+            level,
+            score,
+            reason,
+            added_by: "system".to_string(),
+            added_at: now,
+            updated_at: now,
+            hold_until: now + hold,
+            manual: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum WatchlistChange {
+    Added { entry: WatchlistEntry },
+    /// Level raised, or lowered from critical to elevated
+    Changed { entry: WatchlistEntry, previous: WatchLevel },
+    Removed { entity: EntityId, reason: String },
+}
+
+impl WatchlistChange {
+    pub fn entity(&self) -> &EntityId {
+        match self {
+            Self::Added { entry } | Self::Changed { entry, .. } => &entry.entity,
+            Self::Removed { entity, .. } => entity,
+        }
+    }
+}
+
+/// Receives watchlist changes, e.g. to tighten access decisions
+#[async_trait::async_trait]
+pub trait WatchlistSink: Send + Sync {
+    fn name(&self) -> &str;
+    async fn apply(&self, change: &WatchlistChange) -> Result<(), String>;
+}
+
+/// Posts each change as JSON to an HTTP endpoint
+pub struct WebhookWatchlistSink {
+    url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WebhookWatchlistSink {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            token: None,
+            client: reqwest::Client::builder()
+                .timeout(StdDuration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Bearer token sent with every request
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl WatchlistSink for WebhookWatchlistSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn apply(&self, change: &WatchlistChange) -> Result<(), String> {
+        let mut request = self.client.post(&self.url).json(change);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("{} returned {}", self.url, response.status()));
+        }
+        Ok(())
+    }
+}
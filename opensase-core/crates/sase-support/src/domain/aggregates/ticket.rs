@@ -1,6 +1,6 @@
 //! Ticket Aggregate
 use chrono::{DateTime, Utc};
-use crate::domain::value_objects::{TicketId, Priority, TicketType, SlaPolicy};
+use crate::domain::value_objects::{TicketId, Priority, TicketType, SlaPolicy, SatisfactionRating};
 use crate::domain::events::{DomainEvent, TicketEvent};
 
 #[derive(Clone, Debug)]
@@ -10,7 +10,8 @@ pub struct Ticket {
     assignee_id: Option<String>, group_id: Option<String>, tags: Vec<String>,
     comments: Vec<Comment>, sla: Option<SlaPolicy>, sla_breach_at: Option<DateTime<Utc>>,
     first_responded_at: Option<DateTime<Utc>>, created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>, solved_at: Option<DateTime<Utc>>, events: Vec<DomainEvent>,
+    updated_at: DateTime<Utc>, solved_at: Option<DateTime<Utc>>, organization_id: Option<String>,
+    satisfaction: SatisfactionRating, events: Vec<DomainEvent>,
 }
 
 #[derive(Clone, Debug)] pub struct Comment { pub id: String, pub author_id: String, pub body: String, pub is_public: bool, pub created_at: DateTime<Utc> }
@@ -23,7 +24,8 @@ impl Ticket {
             id: id.clone(), subject: subject.into(), description: description.into(), status: TicketStatus::New,
             priority: Priority::Normal, ticket_type: TicketType::Question, requester_id: requester_id.into(),
             assignee_id: None, group_id: None, tags: vec![], comments: vec![], sla: None, sla_breach_at: None,
-            first_responded_at: None, created_at: now, updated_at: now, solved_at: None, events: vec![],
+            first_responded_at: None, created_at: now, updated_at: now, solved_at: None, organization_id: None,
+            satisfaction: SatisfactionRating::Unoffered, events: vec![],
         };
         t.raise_event(DomainEvent::Ticket(TicketEvent::Created { ticket_id: id }));
         t
@@ -32,6 +34,13 @@ impl Ticket {
     pub fn id(&self) -> &TicketId { &self.id }
     pub fn status(&self) -> &TicketStatus { &self.status }
     pub fn priority(&self) -> &Priority { &self.priority }
+    pub fn requester_id(&self) -> &str { &self.requester_id }
+    pub fn assignee_id(&self) -> Option<&str> { self.assignee_id.as_deref() }
+    pub fn group_id(&self) -> Option<&str> { self.group_id.as_deref() }
+    pub fn organization_id(&self) -> Option<&str> { self.organization_id.as_deref() }
+    pub fn tags(&self) -> &[String] { &self.tags }
+    pub fn solved_at(&self) -> Option<DateTime<Utc>> { self.solved_at }
+    pub fn satisfaction(&self) -> &SatisfactionRating { &self.satisfaction }
     
     pub fn assign(&mut self, agent_id: impl Into<String>) {
         self.assignee_id = Some(agent_id.into());
@@ -53,15 +62,33 @@ impl Ticket {
     pub fn reopen(&mut self) { if self.status == TicketStatus::Solved || self.status == TicketStatus::Closed { self.status = TicketStatus::Open; self.solved_at = None; self.touch(); } }
     pub fn set_priority(&mut self, priority: Priority) { self.priority = priority; self.touch(); }
     pub fn escalate(&mut self) { self.priority = Priority::Urgent; self.touch(); }
+    pub fn set_group(&mut self, group_id: impl Into<String>) { self.group_id = Some(group_id.into()); self.touch(); }
+    pub fn set_organization(&mut self, organization_id: impl Into<String>) { self.organization_id = Some(organization_id.into()); self.touch(); }
+    pub fn add_tag(&mut self, tag: impl Into<String>) { let tag = tag.into(); if !self.tags.contains(&tag) { self.tags.push(tag); self.touch(); } }
+
+    /// Mark that a CSAT survey went out for this ticket
+    pub fn offer_satisfaction(&mut self) {
+        if self.satisfaction == SatisfactionRating::Unoffered { self.satisfaction = SatisfactionRating::Offered; self.touch(); }
+    }
+
+    /// Record the requester's rating; later responses overwrite earlier ones
+    pub fn rate_satisfaction(&mut self, rating: SatisfactionRating) -> Result<(), TicketError> {
+        if !rating.is_rated() { return Err(TicketError::InvalidRating); }
+        if self.solved_at.is_none() { return Err(TicketError::NotSolved); }
+        self.satisfaction = rating.clone();
+        self.touch();
+        self.raise_event(DomainEvent::Ticket(TicketEvent::SatisfactionRated { ticket_id: self.id.clone(), rating }));
+        Ok(())
+    }
     
     pub fn take_events(&mut self) -> Vec<DomainEvent> { std::mem::take(&mut self.events) }
     fn raise_event(&mut self, e: DomainEvent) { self.events.push(e); }
     fn touch(&mut self) { self.updated_at = Utc::now(); }
 }
 
-#[derive(Debug, Clone)] pub enum TicketError { AlreadySolved }
+#[derive(Debug, Clone)] pub enum TicketError { AlreadySolved, NotSolved, InvalidRating }
 impl std::error::Error for TicketError {}
-impl std::fmt::Display for TicketError { fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "Ticket error") } }
+impl std::fmt::Display for TicketError { fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "Ticket error: {:?}", self) } }
 
 #[cfg(test)]
 mod tests {
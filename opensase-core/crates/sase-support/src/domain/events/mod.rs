@@ -1,8 +1,8 @@
 //! Support domain events
-use crate::domain::value_objects::TicketId;
+use crate::domain::value_objects::{TicketId, SatisfactionRating};
 
 #[derive(Clone, Debug)]
-pub enum DomainEvent { Ticket(TicketEvent) }
+pub enum DomainEvent { Ticket(TicketEvent), Survey(SurveyEvent) }
 
 #[derive(Clone, Debug)]
 pub enum TicketEvent {
@@ -11,4 +11,14 @@ pub enum TicketEvent {
     Solved { ticket_id: TicketId },
     Escalated { ticket_id: TicketId },
     SlaBreach { ticket_id: TicketId },
+    SatisfactionRated { ticket_id: TicketId, rating: SatisfactionRating },
+}
+
+#[derive(Clone, Debug)]
+pub enum SurveyEvent {
+    Scheduled { survey_id: String },
+    Sent { survey_id: String },
+    Suppressed { survey_id: String, reason: String },
+    Responded { survey_id: String },
+    NpsCampaignRun { campaign_id: String, sent: u32 },
 }
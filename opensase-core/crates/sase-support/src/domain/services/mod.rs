@@ -0,0 +1,6 @@
+//! Domain services
+pub mod survey;
+pub use survey::{
+    SurveyEngine, SurveyRules, Survey, SurveyKind, SurveyResponse, SurveySender, AccountHealthSink, SatisfactionSignal,
+    NpsCampaign, NpsRecipient, SatisfactionSummary, NpsSummary, SurveyError,
+};
@@ -0,0 +1,381 @@
+//! CSAT and NPS survey engine
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use crate::domain::aggregates::{Ticket, TicketError};
+use crate::domain::value_objects::{TicketId, SatisfactionRating};
+use crate::domain::events::{DomainEvent, SurveyEvent};
+
+/// Automatic send rules
+#[derive(Clone, Debug)]
+pub struct SurveyRules {
+    pub csat_enabled: bool,
+    /// Wait after a ticket is solved before sending CSAT
+    pub csat_delay: Duration,
+    /// Minimum gap between CSAT surveys to the same requester
+    pub csat_frequency_cap: Duration,
+    /// Tickets carrying any of these tags are never surveyed
+    pub csat_skip_tags: Vec<String>,
+    /// Minimum gap between NPS surveys to the same contact
+    pub nps_frequency_cap: Duration,
+    /// How long a sent survey accepts responses
+    pub response_window: Duration,
+}
+impl Default for SurveyRules {
+    fn default() -> Self {
+        Self {
+            csat_enabled: true, csat_delay: Duration::hours(24), csat_frequency_cap: Duration::days(7),
+            csat_skip_tags: vec!["no_survey".into()], nps_frequency_cap: Duration::days(90), response_window: Duration::days(14),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SurveyKind {
+    Csat { ticket_id: TicketId, assignee_id: Option<String>, group_id: Option<String> },
+    Nps { campaign_id: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SurveyResponse {
+    Csat { good: bool, comment: Option<String>, responded_at: DateTime<Utc> },
+    /// 0-10 likelihood to recommend
+    Nps { score: u8, comment: Option<String>, responded_at: DateTime<Utc> },
+}
+
+#[derive(Clone, Debug)]
+pub struct Survey {
+    pub id: String, pub kind: SurveyKind, pub recipient: String, pub account_id: Option<String>,
+    pub send_at: DateTime<Utc>, pub sent_at: Option<DateTime<Utc>>, pub expires_at: Option<DateTime<Utc>>,
+    pub response: Option<SurveyResponse>,
+}
+
+/// Outbound delivery, implemented over the marketing platform's sender
+pub trait SurveySender: Send + Sync {
+    fn send(&self, survey: &Survey) -> Result<(), SurveyError>;
+}
+
+/// Port into account health scoring (CRM)
+pub trait AccountHealthSink: Send + Sync {
+    fn record_satisfaction(&self, account_id: &str, signal: SatisfactionSignal);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SatisfactionSignal { Csat { good: bool }, Nps { score: u8 } }
+
+#[derive(Clone, Debug)]
+pub struct NpsRecipient { pub contact_id: String, pub email: String, pub account_id: Option<String> }
+
+/// Recurring NPS campaign
+#[derive(Clone, Debug)]
+pub struct NpsCampaign {
+    pub id: String, pub name: String, pub interval: Duration, pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>, pub recipients: Vec<NpsRecipient>,
+}
+
+/// CSAT results for one agent or group
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SatisfactionSummary { pub key: String, pub sent: u32, pub responses: u32, pub good: u32, pub bad: u32 }
+impl SatisfactionSummary {
+    /// Percentage of ratings that were good
+    pub fn score(&self) -> Option<f64> { (self.responses > 0).then(|| self.good as f64 * 100.0 / self.responses as f64) }
+    pub fn response_rate(&self) -> Option<f64> { (self.sent > 0).then(|| self.responses as f64 / self.sent as f64) }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NpsSummary { pub sent: u32, pub responses: u32, pub promoters: u32, pub passives: u32, pub detractors: u32 }
+impl NpsSummary {
+    /// % promoters minus % detractors, -100 to 100
+    pub fn score(&self) -> Option<f64> {
+        (self.responses > 0).then(|| (self.promoters as f64 - self.detractors as f64) * 100.0 / self.responses as f64)
+    }
+}
+
+/// Schedules, sends and ingests satisfaction surveys
+pub struct SurveyEngine {
+    rules: SurveyRules,
+    sender: Box<dyn SurveySender>,
+    health: Box<dyn AccountHealthSink>,
+    surveys: DashMap<String, Survey>,
+    last_csat: DashMap<String, DateTime<Utc>>,
+    last_nps: DashMap<String, DateTime<Utc>>,
+    campaigns: DashMap<String, NpsCampaign>,
+    events: std::sync::Mutex<Vec<DomainEvent>>,
+}
+
+impl SurveyEngine {
+    pub fn new(rules: SurveyRules, sender: Box<dyn SurveySender>, health: Box<dyn AccountHealthSink>) -> Self {
+        Self {
+            rules, sender, health, surveys: DashMap::new(), last_csat: DashMap::new(), last_nps: DashMap::new(),
+            campaigns: DashMap::new(), events: std::sync::Mutex::new(vec![]),
+        }
+    }
+
+    pub fn rules(&self) -> &SurveyRules { &self.rules }
+    pub fn survey(&self, survey_id: &str) -> Option<Survey> { self.surveys.get(survey_id).map(|s| s.clone()) }
+    pub fn campaign(&self, campaign_id: &str) -> Option<NpsCampaign> { self.campaigns.get(campaign_id).map(|c| c.clone()) }
+
+    /// Schedule CSAT for a freshly solved ticket; returns the survey ID
+    pub fn on_ticket_solved(&self, ticket: &Ticket) -> Option<String> {
+        let solved_at = ticket.solved_at()?;
+        if !self.rules.csat_enabled || ticket.satisfaction() != &SatisfactionRating::Unoffered { return None; }
+        if ticket.tags().iter().any(|t| self.rules.csat_skip_tags.contains(t)) { return None; }
+        if self.pending_csat(ticket.id()).is_some() { return None; }
+
+        let survey = Survey {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: SurveyKind::Csat { ticket_id: ticket.id().clone(), assignee_id: ticket.assignee_id().map(|a| a.to_string()), group_id: ticket.group_id().map(|g| g.to_string()) },
+            recipient: ticket.requester_id().to_string(), account_id: ticket.organization_id().map(|o| o.to_string()),
+            send_at: solved_at + self.rules.csat_delay, sent_at: None, expires_at: None, response: None,
+        };
+        let id = survey.id.clone();
+        self.surveys.insert(id.clone(), survey);
+        self.raise(SurveyEvent::Scheduled { survey_id: id.clone() });
+        Some(id)
+    }
+
+    /// Drop an unsent CSAT survey, e.g. when the ticket is reopened
+    pub fn cancel_for_ticket(&self, ticket_id: &TicketId) -> bool {
+        let Some(id) = self.pending_csat(ticket_id) else { return false };
+        self.surveys.remove(&id);
+        self.raise(SurveyEvent::Suppressed { survey_id: id, reason: "ticket reopened".into() });
+        true
+    }
+
+    /// Send CSAT surveys whose delay has elapsed. Returns the surveys sent so
+    /// their tickets can be marked offered; failed sends are retried next run.
+    pub fn dispatch_due(&self, now: DateTime<Utc>) -> Vec<Survey> {
+        let mut due: Vec<Survey> = self.surveys.iter()
+            .filter(|s| s.sent_at.is_none() && s.send_at <= now && matches!(s.kind, SurveyKind::Csat { .. }))
+            .map(|s| s.clone())
+            .collect();
+        due.sort_by_key(|s| s.send_at);
+
+        let mut sent = vec![];
+        for survey in due {
+            let capped = self.last_csat.get(&survey.recipient).is_some_and(|last| *last + self.rules.csat_frequency_cap > now);
+            if capped {
+                self.surveys.remove(&survey.id);
+                self.raise(SurveyEvent::Suppressed { survey_id: survey.id, reason: "frequency cap".into() });
+                continue;
+            }
+            if let Some(survey) = self.deliver(survey, now) {
+                self.last_csat.insert(survey.recipient.clone(), now);
+                sent.push(survey);
+            }
+        }
+        sent
+    }
+
+    /// Create a recurring NPS campaign; returns the campaign ID
+    pub fn schedule_nps(&self, name: impl Into<String>, interval: Duration, first_run_at: DateTime<Utc>, recipients: Vec<NpsRecipient>) -> String {
+        let campaign = NpsCampaign { id: uuid::Uuid::new_v4().to_string(), name: name.into(), interval, next_run_at: first_run_at, last_run_at: None, recipients };
+        let id = campaign.id.clone();
+        self.campaigns.insert(id.clone(), campaign);
+        id
+    }
+
+    pub fn set_nps_recipients(&self, campaign_id: &str, recipients: Vec<NpsRecipient>) -> Result<(), SurveyError> {
+        self.campaigns.get_mut(campaign_id).ok_or(SurveyError::CampaignNotFound)?.recipients = recipients;
+        Ok(())
+    }
+
+    /// Run every NPS campaign that is due; returns the number of surveys sent
+    pub fn run_due_nps(&self, now: DateTime<Utc>) -> u32 {
+        let due: Vec<NpsCampaign> = self.campaigns.iter().filter(|c| c.next_run_at <= now).map(|c| c.clone()).collect();
+        let mut total = 0;
+        for campaign in due {
+            let mut sent = 0;
+            for recipient in &campaign.recipients {
+                if self.last_nps.get(&recipient.contact_id).is_some_and(|last| *last + self.rules.nps_frequency_cap > now) { continue; }
+                let survey = Survey {
+                    id: uuid::Uuid::new_v4().to_string(), kind: SurveyKind::Nps { campaign_id: campaign.id.clone() },
+                    recipient: recipient.email.clone(), account_id: recipient.account_id.clone(),
+                    send_at: now, sent_at: None, expires_at: None, response: None,
+                };
+                // Not retried; the contact is picked up on the next run
+                if self.deliver(survey, now).is_some() {
+                    self.last_nps.insert(recipient.contact_id.clone(), now);
+                    sent += 1;
+                }
+            }
+
+            if let Some(mut c) = self.campaigns.get_mut(&campaign.id) {
+                c.last_run_at = Some(now);
+                let interval = c.interval;
+                while c.next_run_at <= now && interval > Duration::zero() { c.next_run_at += interval; }
+            }
+            self.raise(SurveyEvent::NpsCampaignRun { campaign_id: campaign.id, sent });
+            total += sent;
+        }
+        total
+    }
+
+    /// Record a CSAT response and rate the ticket
+    pub fn ingest_csat(&self, survey_id: &str, ticket: &mut Ticket, good: bool, comment: Option<String>) -> Result<(), SurveyError> {
+        let now = Utc::now();
+        let account_id = {
+            let mut survey = self.surveys.get_mut(survey_id).ok_or(SurveyError::SurveyNotFound)?;
+            match &survey.kind {
+                SurveyKind::Csat { ticket_id, .. } if ticket_id == ticket.id() => {}
+                _ => return Err(SurveyError::WrongTicket),
+            }
+            Self::check_open(&survey, now)?;
+
+            let rating = if good { SatisfactionRating::Good { comment: comment.clone() } } else { SatisfactionRating::Bad { comment: comment.clone() } };
+            ticket.rate_satisfaction(rating).map_err(SurveyError::Ticket)?;
+            survey.response = Some(SurveyResponse::Csat { good, comment, responded_at: now });
+            survey.account_id.clone()
+        };
+
+        if let Some(account_id) = account_id { self.health.record_satisfaction(&account_id, SatisfactionSignal::Csat { good }); }
+        self.raise(SurveyEvent::Responded { survey_id: survey_id.to_string() });
+        Ok(())
+    }
+
+    /// Record an NPS response (0-10)
+    pub fn ingest_nps(&self, survey_id: &str, score: u8, comment: Option<String>) -> Result<(), SurveyError> {
+        if score > 10 { return Err(SurveyError::InvalidScore); }
+        let now = Utc::now();
+        let account_id = {
+            let mut survey = self.surveys.get_mut(survey_id).ok_or(SurveyError::SurveyNotFound)?;
+            if !matches!(survey.kind, SurveyKind::Nps { .. }) { return Err(SurveyError::SurveyNotFound); }
+            Self::check_open(&survey, now)?;
+            survey.response = Some(SurveyResponse::Nps { score, comment, responded_at: now });
+            survey.account_id.clone()
+        };
+
+        if let Some(account_id) = account_id { self.health.record_satisfaction(&account_id, SatisfactionSignal::Nps { score }); }
+        self.raise(SurveyEvent::Responded { survey_id: survey_id.to_string() });
+        Ok(())
+    }
+
+    /// CSAT per assigned agent for surveys sent in `[from, to)`
+    pub fn satisfaction_by_agent(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<SatisfactionSummary> {
+        self.csat_summary(from, to, |kind| match kind { SurveyKind::Csat { assignee_id, .. } => assignee_id.clone(), _ => None })
+    }
+
+    /// CSAT per ticket group for surveys sent in `[from, to)`
+    pub fn satisfaction_by_group(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<SatisfactionSummary> {
+        self.csat_summary(from, to, |kind| match kind { SurveyKind::Csat { group_id, .. } => group_id.clone(), _ => None })
+    }
+
+    /// NPS for surveys sent in `[from, to)`, optionally limited to one campaign
+    pub fn nps_summary(&self, campaign_id: Option<&str>, from: DateTime<Utc>, to: DateTime<Utc>) -> NpsSummary {
+        let mut summary = NpsSummary::default();
+        for survey in self.surveys.iter() {
+            let SurveyKind::Nps { campaign_id: c } = &survey.kind else { continue };
+            if campaign_id.is_some_and(|id| id != c) || !survey.sent_at.is_some_and(|t| t >= from && t < to) { continue; }
+            summary.sent += 1;
+            if let Some(SurveyResponse::Nps { score, .. }) = survey.response {
+                summary.responses += 1;
+                match score { 9..=10 => summary.promoters += 1, 7..=8 => summary.passives += 1, _ => summary.detractors += 1 }
+            }
+        }
+        summary
+    }
+
+    /// Drain survey domain events
+    pub fn take_events(&self) -> Vec<DomainEvent> { std::mem::take(&mut *self.events.lock().unwrap()) }
+
+    fn csat_summary(&self, from: DateTime<Utc>, to: DateTime<Utc>, key: impl Fn(&SurveyKind) -> Option<String>) -> Vec<SatisfactionSummary> {
+        let mut by_key: BTreeMap<String, SatisfactionSummary> = BTreeMap::new();
+        for survey in self.surveys.iter() {
+            if !survey.sent_at.is_some_and(|t| t >= from && t < to) { continue; }
+            let Some(k) = key(&survey.kind) else { continue };
+            let entry = by_key.entry(k.clone()).or_insert_with(|| SatisfactionSummary { key: k, ..Default::default() });
+            entry.sent += 1;
+            if let Some(SurveyResponse::Csat { good, .. }) = survey.response {
+                entry.responses += 1;
+                if good { entry.good += 1 } else { entry.bad += 1 }
+            }
+        }
+        by_key.into_values().collect()
+    }
+
+    fn deliver(&self, mut survey: Survey, now: DateTime<Utc>) -> Option<Survey> {
+        if let Err(e) = self.sender.send(&survey) {
+            tracing::warn!("Survey {} to {} not sent: {}", survey.id, survey.recipient, e);
+            return None;
+        }
+        survey.sent_at = Some(now);
+        survey.expires_at = Some(now + self.rules.response_window);
+        self.surveys.insert(survey.id.clone(), survey.clone());
+        self.raise(SurveyEvent::Sent { survey_id: survey.id.clone() });
+        Some(survey)
+    }
+
+    fn pending_csat(&self, ticket_id: &TicketId) -> Option<String> {
+        self.surveys.iter()
+            .find(|s| s.sent_at.is_none() && matches!(&s.kind, SurveyKind::Csat { ticket_id: t, .. } if t == ticket_id))
+            .map(|s| s.id.clone())
+    }
+
+    fn check_open(survey: &Survey, now: DateTime<Utc>) -> Result<(), SurveyError> {
+        if survey.sent_at.is_none() { return Err(SurveyError::NotSent); }
+        if survey.expires_at.is_some_and(|e| e <= now) { return Err(SurveyError::Expired); }
+        Ok(())
+    }
+
+    fn raise(&self, e: SurveyEvent) { self.events.lock().unwrap().push(DomainEvent::Survey(e)); }
+}
+
+#[derive(Debug, Clone)] pub enum SurveyError { SurveyNotFound, CampaignNotFound, WrongTicket, NotSent, Expired, InvalidScore, Ticket(TicketError), DeliveryFailed(String) }
+impl std::error::Error for SurveyError {}
+impl std::fmt::Display for SurveyError { fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "Survey error: {:?}", self) } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Outbox(Arc<Mutex<Vec<String>>>);
+    impl SurveySender for Outbox { fn send(&self, s: &Survey) -> Result<(), SurveyError> { self.0.lock().unwrap().push(s.recipient.clone()); Ok(()) } }
+    struct Health(Arc<Mutex<Vec<(String, SatisfactionSignal)>>>);
+    impl AccountHealthSink for Health { fn record_satisfaction(&self, a: &str, s: SatisfactionSignal) { self.0.lock().unwrap().push((a.to_string(), s)); } }
+
+    fn solved(id: u64, requester: &str) -> Ticket {
+        let mut t = Ticket::create(TicketId::new(id), "Help", "Description", requester);
+        t.assign("agent001");
+        t.set_group("tier1");
+        t.set_organization("acme");
+        t.solve();
+        t
+    }
+
+    #[test]
+    fn test_csat_and_nps() {
+        let outbox = Arc::new(Mutex::new(vec![]));
+        let health = Arc::new(Mutex::new(vec![]));
+        let engine = SurveyEngine::new(SurveyRules::default(), Box::new(Outbox(outbox.clone())), Box::new(Health(health.clone())));
+
+        let mut first = solved(1, "user@example.com");
+        let second = solved(2, "user@example.com");
+        let survey_id = engine.on_ticket_solved(&first).unwrap();
+        engine.on_ticket_solved(&second).unwrap();
+        assert!(engine.dispatch_due(Utc::now()).is_empty());
+
+        // Second survey to the same requester is capped
+        let sent = engine.dispatch_due(Utc::now() + Duration::hours(25));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(outbox.lock().unwrap().len(), 1);
+
+        let survey_id = &sent.iter().find(|s| s.id == survey_id).unwrap().id;
+        first.offer_satisfaction();
+        engine.ingest_csat(survey_id, &mut first, true, Some("Quick fix".into())).unwrap();
+        assert!(first.satisfaction().is_rated());
+        let now = Utc::now() + Duration::days(2);
+        let agents = engine.satisfaction_by_agent(now - Duration::days(3), now);
+        assert_eq!(agents[0].score(), Some(100.0));
+
+        let recipients = vec![NpsRecipient { contact_id: "c1".into(), email: "user@example.com".into(), account_id: Some("acme".into()) }];
+        let campaign = engine.schedule_nps("Quarterly NPS", Duration::days(90), Utc::now(), recipients);
+        assert_eq!(engine.run_due_nps(Utc::now()), 1);
+        assert_eq!(engine.run_due_nps(Utc::now()), 0);
+        let nps = engine.surveys.iter().find(|s| matches!(s.kind, SurveyKind::Nps { .. })).unwrap().id.clone();
+        engine.ingest_nps(&nps, 3, None).unwrap();
+        let summary = engine.nps_summary(Some(&campaign), Utc::now() - Duration::hours(1), Utc::now() + Duration::hours(1));
+        assert_eq!(summary.score(), Some(-100.0));
+        assert_eq!(health.lock().unwrap().len(), 2);
+    }
+}
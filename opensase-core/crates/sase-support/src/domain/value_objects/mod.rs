@@ -26,3 +26,10 @@ impl SlaPolicy {
     pub fn standard() -> Self { Self { name: "Standard".into(), first_response_hours: 24, resolution_hours: 72 } }
     pub fn premium() -> Self { Self { name: "Premium".into(), first_response_hours: 4, resolution_hours: 24 } }
 }
+
+/// Customer satisfaction on a solved ticket
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum SatisfactionRating { #[default] Unoffered, Offered, Good { comment: Option<String> }, Bad { comment: Option<String> } }
+impl SatisfactionRating {
+    pub fn is_rated(&self) -> bool { matches!(self, Self::Good { .. } | Self::Bad { .. }) }
+}
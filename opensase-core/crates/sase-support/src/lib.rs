@@ -1,5 +1,6 @@
 //! OpenSASE Support Platform - DDD Implementation (Zendesk replacement)
 pub mod domain;
 pub use domain::aggregates::{Ticket, Agent, TicketError};
-pub use domain::value_objects::TicketId;
-pub use domain::events::{DomainEvent, TicketEvent};
+pub use domain::value_objects::{TicketId, SatisfactionRating};
+pub use domain::events::{DomainEvent, TicketEvent, SurveyEvent};
+pub use domain::services::{SurveyEngine, SurveyRules, SurveySender, AccountHealthSink, SurveyError};
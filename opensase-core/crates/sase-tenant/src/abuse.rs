@@ -0,0 +1,1080 @@
+//! Trial Tenant Abuse Detection
+//!
+//! Free trials are a cheap way to get SASE egress capacity, so they attract
+//! scanning, spam and crypto-mining. This module scores trial tenants from
+//! egress telemetry and signup identity signals, throttles or suspends them,
+//! runs the appeals workflow, and exports confirmed abuse indicators for the
+//! threat intelligence platform.
+
+use crate::lifecycle::{LifecycleError, TenantRegistry};
+use crate::limits::QuotaEnforcer;
+use crate::model::{ResourceLimits, TenantId, TenantTier};
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Well-known stratum mining ports
+const MINING_PORTS: &[u16] = &[3333, 3334, 4444, 5555, 7777, 8888, 9999, 14433, 14444, 45700];
+
+/// Pool domain fragments seen in crypto-mining egress
+const MINING_POOL_MARKERS: &[&str] = &[
+    "pool.", "nanopool", "minexmr", "supportxmr", "2miners", "f2pool",
+    "ethermine", "hashvault", "moneroocean", "nicehash", "unmineable",
+];
+
+/// Disposable email providers commonly used for throwaway trials
+const DISPOSABLE_EMAIL_DOMAINS: &[&str] = &[
+    "mailinator.com", "guerrillamail.com", "10minutemail.com", "tempmail.com",
+    "trashmail.com", "yopmail.com", "sharklasers.com", "getnada.com",
+];
+
+/// Abuse detection thresholds
+#[derive(Debug, Clone)]
+pub struct AbuseConfig {
+    /// Sliding window for egress signals (seconds)
+    pub window_secs: u64,
+    /// Distinct destination IPs per window that indicates horizontal scanning
+    pub scan_distinct_hosts: usize,
+    /// Distinct destination ports on one host that indicates vertical scanning
+    pub scan_distinct_ports: usize,
+    /// Failed/reset connection ratio above which scanning is likely
+    pub scan_failure_ratio: f64,
+    /// Outbound SMTP messages per window
+    pub smtp_messages: u64,
+    /// Distinct SMTP recipient domains per window
+    pub smtp_recipient_domains: usize,
+    /// Trials sharing one signup IP or payment fingerprint
+    pub max_trials_per_identity: usize,
+    /// Score at which the tenant is throttled
+    pub throttle_score: u32,
+    /// Score at which the tenant is suspended
+    pub suspend_score: u32,
+}
+
+impl Default for AbuseConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 300,
+            scan_distinct_hosts: 500,
+            scan_distinct_ports: 100,
+            scan_failure_ratio: 0.8,
+            smtp_messages: 500,
+            smtp_recipient_domains: 200,
+            max_trials_per_identity: 3,
+            throttle_score: 50,
+            suspend_score: 100,
+        }
+    }
+}
+
+/// Identity captured at trial signup
+#[derive(Debug, Clone)]
+pub struct TrialSignup {
+    /// Trial tenant
+    pub tenant_id: TenantId,
+    /// Signup email address
+    pub email: String,
+    /// IP the signup came from
+    pub signup_ip: IpAddr,
+    /// Country claimed in the signup form
+    pub declared_country: String,
+    /// Country resolved from the signup IP
+    pub ip_country: Option<String>,
+    /// Card/payment fingerprint, if one was collected
+    pub payment_fingerprint: Option<String>,
+    /// Browser/device fingerprint
+    pub device_fingerprint: Option<String>,
+    /// Signup time (unix seconds)
+    pub created_at: u64,
+}
+
+/// Egress flow summary reported by the dataplane for a trial tenant
+#[derive(Debug, Clone)]
+pub struct EgressFlow {
+    /// Trial tenant
+    pub tenant_id: TenantId,
+    /// Tenant overlay address that opened the flow
+    pub src_ip: IpAddr,
+    /// Destination address
+    pub dst_ip: IpAddr,
+    /// Destination port
+    pub dst_port: u16,
+    /// SNI or DNS name, if known
+    pub server_name: Option<String>,
+    /// Connection never completed (SYN without SYN-ACK, RST)
+    pub failed: bool,
+    /// First payload bytes contained a stratum `mining.subscribe`/`login` call
+    pub stratum_handshake: bool,
+    /// Flow start (unix seconds)
+    pub timestamp: u64,
+}
+
+/// Outbound mail event from the email gateway
+#[derive(Debug, Clone)]
+pub struct OutboundMail {
+    /// Trial tenant
+    pub tenant_id: TenantId,
+    /// Domains of the message recipients
+    pub recipient_domains: Vec<String>,
+    /// Submission time (unix seconds)
+    pub timestamp: u64,
+}
+
+/// Kind of abuse signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbuseSignal {
+    /// Many hosts probed, mostly failing
+    HorizontalScan,
+    /// Many ports probed on one host
+    VerticalScan,
+    /// Outbound mail volume or recipient spread above limits
+    MassMail,
+    /// Egress to a mining pool or a stratum handshake
+    CryptoMining,
+    /// Signup with a disposable email domain
+    DisposableEmail,
+    /// Too many trials from one signup IP
+    SharedSignupIp,
+    /// Too many trials with one payment fingerprint
+    SharedPaymentFingerprint,
+    /// Too many trials from one device
+    SharedDevice,
+    /// Declared country differs from the signup IP's country
+    CountryMismatch,
+}
+
+impl AbuseSignal {
+    /// Score contributed by this signal
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::HorizontalScan | Self::VerticalScan => 60,
+            Self::MassMail => 60,
+            Self::CryptoMining => 100,
+            Self::DisposableEmail => 20,
+            Self::SharedSignupIp => 25,
+            Self::SharedPaymentFingerprint => 40,
+            Self::SharedDevice => 25,
+            Self::CountryMismatch => 15,
+        }
+    }
+
+    /// Egress behaviour (as opposed to identity) signals
+    pub fn is_behavioral(&self) -> bool {
+        matches!(self, Self::HorizontalScan | Self::VerticalScan | Self::MassMail | Self::CryptoMining)
+    }
+}
+
+/// Enforcement state of a trial tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AbuseAction {
+    /// No signals
+    None,
+    /// Signals below the throttle score; watched only
+    Monitor,
+    /// Egress limited to throttled limits
+    Throttle,
+    /// Tenant suspended
+    Suspend,
+}
+
+/// Evidence attached to a detection
+#[derive(Debug, Clone)]
+pub struct AbuseEvidence {
+    /// Signal detected
+    pub signal: AbuseSignal,
+    /// Human-readable summary
+    pub detail: String,
+    /// Indicators to export once confirmed
+    pub indicators: Vec<AbuseIndicator>,
+    /// When the signal was last seen (unix seconds)
+    pub observed_at: u64,
+}
+
+/// Indicator exported to threat intelligence once abuse is confirmed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AbuseIndicator {
+    /// Indicator type
+    pub kind: AbuseIndicatorKind,
+    /// IP address, domain or email
+    pub value: String,
+    /// Threat intel tag
+    pub tag: &'static str,
+}
+
+/// Kind of abuse indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbuseIndicatorKind {
+    /// IP address
+    Ip,
+    /// Domain name
+    Domain,
+    /// Email address
+    Email,
+}
+
+/// Abuse case for a trial tenant
+#[derive(Debug, Clone)]
+pub struct AbuseCase {
+    /// Case ID
+    pub case_id: String,
+    /// Trial tenant
+    pub tenant_id: TenantId,
+    /// Sum of signal weights, each signal counted once
+    pub score: u32,
+    /// Enforcement in force
+    pub action: AbuseAction,
+    /// Evidence, one entry per signal
+    pub evidence: Vec<AbuseEvidence>,
+    /// Confirmed by an analyst; indicators were exported
+    pub confirmed: bool,
+    /// Case opened (unix seconds)
+    pub opened_at: u64,
+    /// Last evidence or status change (unix seconds)
+    pub updated_at: u64,
+}
+
+/// Appeal status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppealStatus {
+    /// Waiting for a reviewer
+    Submitted,
+    /// Picked up by a reviewer
+    UnderReview,
+    /// Enforcement lifted
+    Approved,
+    /// Enforcement upheld
+    Rejected,
+}
+
+/// Tenant appeal against throttling or suspension
+#[derive(Debug, Clone)]
+pub struct Appeal {
+    /// Appeal ID
+    pub appeal_id: String,
+    /// Case appealed
+    pub case_id: String,
+    /// Appealing tenant
+    pub tenant_id: TenantId,
+    /// Tenant's explanation
+    pub statement: String,
+    /// Review status
+    pub status: AppealStatus,
+    /// Reviewer handling the appeal
+    pub reviewer: Option<String>,
+    /// Reviewer's note on resolution
+    pub resolution_note: Option<String>,
+    /// Submitted (unix seconds)
+    pub submitted_at: u64,
+    /// Resolved (unix seconds)
+    pub resolved_at: Option<u64>,
+}
+
+/// Receives confirmed abuse indicators (implemented by the threat intel bridge)
+pub trait AbuseIntelSink: Send + Sync {
+    /// Publish indicators from a confirmed case
+    fn publish(&self, tenant_id: TenantId, indicators: &[AbuseIndicator]);
+
+    /// Withdraw indicators published for a case that was overturned on appeal
+    fn retract(&self, tenant_id: TenantId, indicators: &[AbuseIndicator]);
+}
+
+/// Per-tenant sliding window of egress activity
+///
+/// Distinct counts are kept alongside the queues and updated as entries enter
+/// and leave the window, so each observation costs O(1) rather than a rescan.
+struct EgressWindow {
+    flows: VecDeque<EgressFlow>,
+    mails: VecDeque<OutboundMail>,
+    failed: usize,
+    /// Flows per tenant source address
+    sources: HashMap<IpAddr, usize>,
+    /// Flows per destination host and port
+    hosts: HashMap<IpAddr, HashMap<u16, usize>>,
+    /// Hosts with at least `port_threshold` distinct ports
+    wide_hosts: HashSet<IpAddr>,
+    port_threshold: usize,
+    /// Recipients per domain
+    domains: HashMap<String, usize>,
+}
+
+impl EgressWindow {
+    fn new(port_threshold: usize) -> Self {
+        Self {
+            flows: VecDeque::new(),
+            mails: VecDeque::new(),
+            failed: 0,
+            sources: HashMap::new(),
+            hosts: HashMap::new(),
+            wide_hosts: HashSet::new(),
+            port_threshold,
+            domains: HashMap::new(),
+        }
+    }
+
+    fn push_flow(&mut self, flow: EgressFlow) {
+        self.failed += usize::from(flow.failed);
+        *self.sources.entry(flow.src_ip).or_default() += 1;
+        let ports = self.hosts.entry(flow.dst_ip).or_default();
+        *ports.entry(flow.dst_port).or_default() += 1;
+        if ports.len() >= self.port_threshold {
+            self.wide_hosts.insert(flow.dst_ip);
+        }
+        self.flows.push_back(flow);
+    }
+
+    fn push_mail(&mut self, mail: OutboundMail) {
+        for domain in &mail.recipient_domains {
+            *self.domains.entry(domain.clone()).or_default() += 1;
+        }
+        self.mails.push_back(mail);
+    }
+
+    fn prune(&mut self, cutoff: u64) {
+        while self.flows.front().is_some_and(|f| f.timestamp < cutoff) {
+            let Some(flow) = self.flows.pop_front() else { break };
+            self.failed -= usize::from(flow.failed);
+            decrement(&mut self.sources, &flow.src_ip);
+            if let Some(ports) = self.hosts.get_mut(&flow.dst_ip) {
+                decrement(ports, &flow.dst_port);
+                if ports.len() < self.port_threshold {
+                    self.wide_hosts.remove(&flow.dst_ip);
+                }
+                if ports.is_empty() {
+                    self.hosts.remove(&flow.dst_ip);
+                }
+            }
+        }
+        while self.mails.front().is_some_and(|m| m.timestamp < cutoff) {
+            let Some(mail) = self.mails.pop_front() else { break };
+            for domain in &mail.recipient_domains {
+                decrement(&mut self.domains, domain);
+            }
+        }
+    }
+}
+
+fn decrement<K: Eq + std::hash::Hash>(counts: &mut HashMap<K, usize>, key: &K) {
+    if let Some(n) = counts.get_mut(key) {
+        *n -= 1;
+        if *n == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+/// Trial abuse detector
+pub struct AbuseDetector {
+    config: AbuseConfig,
+    registry: Arc<TenantRegistry>,
+    quotas: Arc<QuotaEnforcer>,
+    signups: RwLock<HashMap<TenantId, TrialSignup>>,
+    windows: RwLock<HashMap<TenantId, EgressWindow>>,
+    cases: RwLock<HashMap<TenantId, AbuseCase>>,
+    appeals: RwLock<HashMap<String, Appeal>>,
+    /// Tenants cleared on appeal are not re-flagged for identity signals
+    cleared: RwLock<HashSet<TenantId>>,
+    intel_sink: Option<Arc<dyn AbuseIntelSink>>,
+}
+
+impl AbuseDetector {
+    /// Create a detector enforcing through the registry and quota enforcer
+    pub fn new(config: AbuseConfig, registry: Arc<TenantRegistry>, quotas: Arc<QuotaEnforcer>) -> Self {
+        Self {
+            config,
+            registry,
+            quotas,
+            signups: RwLock::new(HashMap::new()),
+            windows: RwLock::new(HashMap::new()),
+            cases: RwLock::new(HashMap::new()),
+            appeals: RwLock::new(HashMap::new()),
+            cleared: RwLock::new(HashSet::new()),
+            intel_sink: None,
+        }
+    }
+
+    /// Forward confirmed abuse indicators to threat intel
+    pub fn with_intel_sink(mut self, sink: Arc<dyn AbuseIntelSink>) -> Self {
+        self.intel_sink = Some(sink);
+        self
+    }
+
+    /// Enroll a trial tenant and evaluate its signup identity
+    pub fn enroll_trial(&self, signup: TrialSignup) -> AbuseAction {
+        let tenant_id = signup.tenant_id;
+        let evidence = self.identity_signals(&signup);
+        self.signups.write().insert(tenant_id, signup);
+        self.record(tenant_id, evidence)
+    }
+
+    /// Stop tracking a tenant (converted to paid or deleted)
+    pub fn release(&self, tenant_id: &TenantId) {
+        self.signups.write().remove(tenant_id);
+        self.windows.write().remove(tenant_id);
+    }
+
+    /// Whether the tenant is a monitored trial
+    pub fn is_trial(&self, tenant_id: &TenantId) -> bool {
+        self.signups.read().contains_key(tenant_id)
+    }
+
+    /// Observe an egress flow from a trial tenant
+    pub fn observe_flow(&self, flow: EgressFlow) -> AbuseAction {
+        let tenant_id = flow.tenant_id;
+        if !self.is_trial(&tenant_id) {
+            return AbuseAction::None;
+        }
+
+        let mut evidence = Vec::new();
+        if let Some(e) = mining_signal(&flow) {
+            evidence.push(e);
+        }
+
+        {
+            let mut windows = self.windows.write();
+            let window = windows.entry(tenant_id)
+                .or_insert_with(|| EgressWindow::new(self.config.scan_distinct_ports));
+            window.prune(flow.timestamp.saturating_sub(self.config.window_secs));
+            window.push_flow(flow);
+            evidence.extend(self.scan_signals(window));
+        }
+
+        self.record(tenant_id, evidence)
+    }
+
+    /// Observe an outbound mail submission from a trial tenant
+    pub fn observe_mail(&self, mail: OutboundMail) -> AbuseAction {
+        let tenant_id = mail.tenant_id;
+        if !self.is_trial(&tenant_id) {
+            return AbuseAction::None;
+        }
+
+        let evidence = {
+            let mut windows = self.windows.write();
+            let window = windows.entry(tenant_id)
+                .or_insert_with(|| EgressWindow::new(self.config.scan_distinct_ports));
+            window.prune(mail.timestamp.saturating_sub(self.config.window_secs));
+            window.push_mail(mail);
+            self.mail_signals(window)
+        };
+
+        self.record(tenant_id, evidence)
+    }
+
+    /// Get the abuse case for a tenant
+    pub fn case(&self, tenant_id: &TenantId) -> Option<AbuseCase> {
+        self.cases.read().get(tenant_id).cloned()
+    }
+
+    /// List open cases at or above an action level
+    pub fn cases_at_least(&self, action: AbuseAction) -> Vec<AbuseCase> {
+        self.cases.read().values().filter(|c| c.action >= action).cloned().collect()
+    }
+
+    /// Analyst confirmation: publish the case indicators to threat intel
+    pub fn confirm(&self, tenant_id: &TenantId) -> Result<Vec<AbuseIndicator>, AbuseError> {
+        let indicators = {
+            let mut cases = self.cases.write();
+            let case = cases.get_mut(tenant_id).ok_or(AbuseError::CaseNotFound)?;
+            case.confirmed = true;
+            case.updated_at = now();
+            confirmed_indicators(case)
+        };
+
+        if let Some(sink) = &self.intel_sink {
+            if !indicators.is_empty() {
+                sink.publish(*tenant_id, &indicators);
+            }
+        }
+
+        Ok(indicators)
+    }
+
+    /// Tenant submits an appeal against an enforcement action
+    pub fn submit_appeal(&self, tenant_id: &TenantId, statement: &str) -> Result<Appeal, AbuseError> {
+        let case_id = {
+            let cases = self.cases.read();
+            let case = cases.get(tenant_id).ok_or(AbuseError::CaseNotFound)?;
+            if case.action < AbuseAction::Throttle {
+                return Err(AbuseError::NothingToAppeal);
+            }
+            case.case_id.clone()
+        };
+
+        let mut appeals = self.appeals.write();
+        if appeals.values().any(|a| a.tenant_id == *tenant_id
+            && matches!(a.status, AppealStatus::Submitted | AppealStatus::UnderReview))
+        {
+            return Err(AbuseError::AppealPending);
+        }
+
+        let appeal = Appeal {
+            appeal_id: uuid::Uuid::new_v4().to_string(),
+            case_id,
+            tenant_id: *tenant_id,
+            statement: statement.to_string(),
+            status: AppealStatus::Submitted,
+            reviewer: None,
+            resolution_note: None,
+            submitted_at: now(),
+            resolved_at: None,
+        };
+        appeals.insert(appeal.appeal_id.clone(), appeal.clone());
+
+        Ok(appeal)
+    }
+
+    /// Reviewer picks up an appeal
+    pub fn start_review(&self, appeal_id: &str, reviewer: &str) -> Result<(), AbuseError> {
+        let mut appeals = self.appeals.write();
+        let appeal = appeals.get_mut(appeal_id).ok_or(AbuseError::AppealNotFound)?;
+        if appeal.status != AppealStatus::Submitted {
+            return Err(AbuseError::InvalidAppealState);
+        }
+        appeal.status = AppealStatus::UnderReview;
+        appeal.reviewer = Some(reviewer.to_string());
+        Ok(())
+    }
+
+    /// Resolve an appeal; approval lifts enforcement, clears the case and
+    /// retracts any indicators it exported
+    pub fn resolve_appeal(&self, appeal_id: &str, approved: bool, note: &str) -> Result<Appeal, AbuseError> {
+        let appeal = {
+            let mut appeals = self.appeals.write();
+            let appeal = appeals.get_mut(appeal_id).ok_or(AbuseError::AppealNotFound)?;
+            if appeal.status != AppealStatus::UnderReview {
+                return Err(AbuseError::InvalidAppealState);
+            }
+            appeal.status = if approved { AppealStatus::Approved } else { AppealStatus::Rejected };
+            appeal.resolution_note = Some(note.to_string());
+            appeal.resolved_at = Some(now());
+            appeal.clone()
+        };
+
+        if approved {
+            self.lift(&appeal.tenant_id)?;
+        }
+
+        Ok(appeal)
+    }
+
+    /// Get an appeal
+    pub fn appeal(&self, appeal_id: &str) -> Option<Appeal> {
+        self.appeals.read().get(appeal_id).cloned()
+    }
+
+    fn lift(&self, tenant_id: &TenantId) -> Result<(), AbuseError> {
+        let case = self.cases.write().remove(tenant_id);
+        let previous = case.as_ref().map(|c| c.action);
+
+        // The confirmation was overturned, so its indicators no longer stand
+        if let (Some(sink), Some(case)) = (&self.intel_sink, case.as_ref().filter(|c| c.confirmed)) {
+            let indicators = confirmed_indicators(case);
+            if !indicators.is_empty() {
+                sink.retract(*tenant_id, &indicators);
+            }
+        }
+        self.cleared.write().insert(*tenant_id);
+        self.windows.write().remove(tenant_id);
+
+        if previous == Some(AbuseAction::Suspend) {
+            match self.registry.reactivate(tenant_id) {
+                // Already reinstated, or offboarded meanwhile: leave it be
+                Ok(()) | Err(LifecycleError::InvalidTransition { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        // Trials run on free-tier limits
+        self.quotas.set_limits(*tenant_id, ResourceLimits::for_tier(TenantTier::Free));
+
+        Ok(())
+    }
+
+    /// Merge new evidence into the tenant case and enforce the resulting action
+    fn record(&self, tenant_id: TenantId, evidence: Vec<AbuseEvidence>) -> AbuseAction {
+        let cleared = self.cleared.read().contains(&tenant_id);
+        let evidence: Vec<_> = evidence.into_iter()
+            .filter(|e| !cleared || e.signal.is_behavioral())
+            .collect();
+
+        let (previous, action) = {
+            let mut cases = self.cases.write();
+            if evidence.is_empty() {
+                return cases.get(&tenant_id).map(|c| c.action).unwrap_or(AbuseAction::None);
+            }
+
+            let case = cases.entry(tenant_id).or_insert_with(|| AbuseCase {
+                case_id: uuid::Uuid::new_v4().to_string(),
+                tenant_id,
+                score: 0,
+                action: AbuseAction::None,
+                evidence: Vec::new(),
+                confirmed: false,
+                opened_at: now(),
+                updated_at: now(),
+            });
+
+            for e in evidence {
+                // Each signal counts once toward the score; repeats refresh its evidence
+                match case.evidence.iter_mut().find(|x| x.signal == e.signal) {
+                    Some(existing) => {
+                        for indicator in e.indicators {
+                            if !existing.indicators.contains(&indicator) {
+                                existing.indicators.push(indicator);
+                            }
+                        }
+                        existing.detail = e.detail;
+                        existing.observed_at = e.observed_at;
+                    }
+                    None => {
+                        case.score += e.signal.weight();
+                        case.evidence.push(e);
+                    }
+                }
+            }
+            case.updated_at = now();
+
+            let previous = case.action;
+            // Enforcement only escalates automatically; de-escalation goes through appeals
+            case.action = previous.max(self.action_for(case.score));
+            (previous, case.action)
+        };
+
+        if action > previous {
+            self.enforce(&tenant_id, action);
+        }
+
+        action
+    }
+
+    fn action_for(&self, score: u32) -> AbuseAction {
+        if score >= self.config.suspend_score {
+            AbuseAction::Suspend
+        } else if score >= self.config.throttle_score {
+            AbuseAction::Throttle
+        } else if score > 0 {
+            AbuseAction::Monitor
+        } else {
+            AbuseAction::None
+        }
+    }
+
+    fn enforce(&self, tenant_id: &TenantId, action: AbuseAction) {
+        match action {
+            AbuseAction::Throttle => {
+                self.quotas.set_limits(*tenant_id, throttled_limits());
+                tracing::warn!(%tenant_id, "trial tenant throttled for suspected abuse");
+            }
+            AbuseAction::Suspend => {
+                self.quotas.set_limits(*tenant_id, throttled_limits());
+                if let Err(e) = self.registry.suspend(tenant_id) {
+                    tracing::error!(%tenant_id, "failed to suspend abusive trial tenant: {}", e);
+                } else {
+                    tracing::warn!(%tenant_id, "trial tenant suspended for abuse");
+                }
+            }
+            AbuseAction::None | AbuseAction::Monitor => {}
+        }
+    }
+
+    fn identity_signals(&self, signup: &TrialSignup) -> Vec<AbuseEvidence> {
+        let mut evidence = Vec::new();
+        let ts = signup.created_at;
+
+        let email_domain = signup.email.rsplit('@').next().unwrap_or_default().to_lowercase();
+        if DISPOSABLE_EMAIL_DOMAINS.contains(&email_domain.as_str()) {
+            evidence.push(AbuseEvidence {
+                signal: AbuseSignal::DisposableEmail,
+                detail: format!("disposable email domain {}", email_domain),
+                indicators: vec![AbuseIndicator { kind: AbuseIndicatorKind::Email, value: signup.email.to_lowercase(), tag: "trial-abuse" }],
+                observed_at: ts,
+            });
+        }
+
+        if let Some(ip_country) = &signup.ip_country {
+            if !ip_country.eq_ignore_ascii_case(&signup.declared_country) {
+                evidence.push(AbuseEvidence {
+                    signal: AbuseSignal::CountryMismatch,
+                    detail: format!("declared {} but signed up from {}", signup.declared_country, ip_country),
+                    indicators: Vec::new(),
+                    observed_at: ts,
+                });
+            }
+        }
+
+        let signups = self.signups.read();
+        let others = || signups.values().filter(|s| s.tenant_id != signup.tenant_id);
+        let limit = self.config.max_trials_per_identity;
+
+        let same_ip = others().filter(|s| s.signup_ip == signup.signup_ip).count();
+        if same_ip + 1 > limit {
+            evidence.push(AbuseEvidence {
+                signal: AbuseSignal::SharedSignupIp,
+                detail: format!("{} trials from signup IP {}", same_ip + 1, signup.signup_ip),
+                indicators: vec![AbuseIndicator { kind: AbuseIndicatorKind::Ip, value: signup.signup_ip.to_string(), tag: "trial-abuse" }],
+                observed_at: ts,
+            });
+        }
+
+        if let Some(fp) = &signup.payment_fingerprint {
+            let same = others().filter(|s| s.payment_fingerprint.as_ref() == Some(fp)).count();
+            if same + 1 > limit {
+                evidence.push(AbuseEvidence {
+                    signal: AbuseSignal::SharedPaymentFingerprint,
+                    detail: format!("payment fingerprint reused across {} trials", same + 1),
+                    indicators: Vec::new(),
+                    observed_at: ts,
+                });
+            }
+        }
+
+        if let Some(fp) = &signup.device_fingerprint {
+            let same = others().filter(|s| s.device_fingerprint.as_ref() == Some(fp)).count();
+            if same + 1 > limit {
+                evidence.push(AbuseEvidence {
+                    signal: AbuseSignal::SharedDevice,
+                    detail: format!("device fingerprint reused across {} trials", same + 1),
+                    indicators: Vec::new(),
+                    observed_at: ts,
+                });
+            }
+        }
+
+        evidence
+    }
+
+    fn scan_signals(&self, window: &EgressWindow) -> Vec<AbuseEvidence> {
+        let mut evidence = Vec::new();
+        let Some(last) = window.flows.back() else { return evidence };
+        let failure_ratio = window.failed as f64 / window.flows.len() as f64;
+
+        if failure_ratio < self.config.scan_failure_ratio {
+            return evidence;
+        }
+
+        let hosts = window.hosts.len();
+        if hosts >= self.config.scan_distinct_hosts {
+            evidence.push(AbuseEvidence {
+                signal: AbuseSignal::HorizontalScan,
+                detail: format!("{} hosts probed, {:.0}% failed", hosts, failure_ratio * 100.0),
+                indicators: source_ips(window),
+                observed_at: last.timestamp,
+            });
+        }
+
+        let widest = window.wide_hosts.iter()
+            .filter_map(|host| window.hosts.get(host).map(|ports| (host, ports.len())))
+            .max_by_key(|(_, ports)| *ports);
+        if let Some((host, ports)) = widest {
+            evidence.push(AbuseEvidence {
+                signal: AbuseSignal::VerticalScan,
+                detail: format!("{} ports probed on {}", ports, host),
+                indicators: source_ips(window),
+                observed_at: last.timestamp,
+            });
+        }
+
+        evidence
+    }
+
+    fn mail_signals(&self, window: &EgressWindow) -> Vec<AbuseEvidence> {
+        let Some(last) = window.mails.back() else { return Vec::new() };
+        let messages = window.mails.len() as u64;
+        let domains = &window.domains;
+
+        if messages < self.config.smtp_messages && domains.len() < self.config.smtp_recipient_domains {
+            return Vec::new();
+        }
+
+        vec![AbuseEvidence {
+            signal: AbuseSignal::MassMail,
+            detail: format!("{} messages to {} domains in {}s", messages, domains.len(), self.config.window_secs),
+            indicators: source_ips(window),
+            observed_at: last.timestamp,
+        }]
+    }
+}
+
+fn mining_signal(flow: &EgressFlow) -> Option<AbuseEvidence> {
+    let pool_name = flow.server_name.as_deref()
+        .map(|n| n.to_lowercase())
+        .filter(|n| MINING_POOL_MARKERS.iter().any(|m| n.contains(m)));
+
+    // A mining port or pool-like name alone is weak; require both, or a stratum handshake
+    let matched = flow.stratum_handshake
+        || (pool_name.is_some() && MINING_PORTS.contains(&flow.dst_port));
+    if !matched {
+        return None;
+    }
+
+    let mut indicators = vec![AbuseIndicator { kind: AbuseIndicatorKind::Ip, value: flow.dst_ip.to_string(), tag: "cryptomining-pool" }];
+    if let Some(name) = &pool_name {
+        indicators.push(AbuseIndicator { kind: AbuseIndicatorKind::Domain, value: name.clone(), tag: "cryptomining-pool" });
+    }
+
+    Some(AbuseEvidence {
+        signal: AbuseSignal::CryptoMining,
+        detail: format!("mining egress to {}:{}{}", flow.dst_ip, flow.dst_port,
+            pool_name.map(|n| format!(" ({})", n)).unwrap_or_default()),
+        indicators,
+        observed_at: flow.timestamp,
+    })
+}
+
+fn source_ips(window: &EgressWindow) -> Vec<AbuseIndicator> {
+    window.sources.keys()
+        .map(|ip| AbuseIndicator { kind: AbuseIndicatorKind::Ip, value: ip.to_string(), tag: "trial-abuse-source" })
+        .collect()
+}
+
+fn confirmed_indicators(case: &AbuseCase) -> Vec<AbuseIndicator> {
+    let mut seen = HashSet::new();
+    case.evidence.iter()
+        .flat_map(|e| e.indicators.iter().cloned())
+        .filter(|i| seen.insert(i.clone()))
+        .collect()
+}
+
+/// Limits applied to throttled trials
+fn throttled_limits() -> ResourceLimits {
+    ResourceLimits {
+        bandwidth_mbps: 1,
+        max_connections: 20,
+        api_rate_limit: 10,
+        ..ResourceLimits::for_tier(TenantTier::Free)
+    }
+}
+
+/// Abuse workflow errors
+#[derive(Debug, thiserror::Error)]
+pub enum AbuseError {
+    /// No case is open for the tenant
+    #[error("no abuse case for tenant")]
+    CaseNotFound,
+    /// Case is below throttling
+    #[error("tenant is not throttled or suspended")]
+    NothingToAppeal,
+    /// Tenant already has an appeal awaiting a decision
+    #[error("an appeal is already pending")]
+    AppealPending,
+    /// No appeal with this ID
+    #[error("appeal not found")]
+    AppealNotFound,
+    /// Appeal is not in the state the step requires
+    #[error("appeal is not in a state that allows this transition")]
+    InvalidAppealState,
+    /// Suspension could not be lifted
+    #[error("lifecycle error: {0}")]
+    Lifecycle(#[from] LifecycleError),
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::IsolationEngine;
+    use crate::model::TenantStatus;
+
+    fn setup() -> (AbuseDetector, Arc<TenantRegistry>, TenantId) {
+        let registry = Arc::new(TenantRegistry::new(Arc::new(IsolationEngine::new())));
+        let quotas = Arc::new(QuotaEnforcer::new());
+        let tenant = registry.create("Trial Co", TenantTier::Free).unwrap();
+        let detector = AbuseDetector::new(AbuseConfig::default(), registry.clone(), quotas);
+
+        detector.enroll_trial(TrialSignup {
+            tenant_id: tenant.tenant_id,
+            email: "ops@trial.example".into(),
+            signup_ip: "198.51.100.10".parse().unwrap(),
+            declared_country: "US".into(),
+            ip_country: Some("US".into()),
+            payment_fingerprint: None,
+            device_fingerprint: None,
+            created_at: now(),
+        });
+
+        (detector, registry, tenant.tenant_id)
+    }
+
+    #[test]
+    fn test_mining_suspends_and_appeal_reinstates() {
+        let (detector, registry, tenant_id) = setup();
+
+        let action = detector.observe_flow(EgressFlow {
+            tenant_id,
+            src_ip: "100.64.0.5".parse().unwrap(),
+            dst_ip: "203.0.113.50".parse().unwrap(),
+            dst_port: 14444,
+            server_name: Some("xmr.supportxmr.com".into()),
+            failed: false,
+            stratum_handshake: true,
+            timestamp: now(),
+        });
+        assert_eq!(action, AbuseAction::Suspend);
+        assert_eq!(registry.get(&tenant_id).unwrap().metadata.status, TenantStatus::Suspended);
+
+        let indicators = detector.confirm(&tenant_id).unwrap();
+        assert!(indicators.iter().any(|i| i.kind == AbuseIndicatorKind::Domain));
+
+        let appeal = detector.submit_appeal(&tenant_id, "security research, approved by customer").unwrap();
+        detector.start_review(&appeal.appeal_id, "trust-and-safety").unwrap();
+        detector.resolve_appeal(&appeal.appeal_id, true, "verified").unwrap();
+
+        assert_eq!(registry.get(&tenant_id).unwrap().metadata.status, TenantStatus::Active);
+        assert!(detector.case(&tenant_id).is_none());
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        published: parking_lot::Mutex<Vec<AbuseIndicator>>,
+        retracted: parking_lot::Mutex<Vec<AbuseIndicator>>,
+    }
+
+    impl AbuseIntelSink for RecordingSink {
+        fn publish(&self, _: TenantId, indicators: &[AbuseIndicator]) {
+            self.published.lock().extend_from_slice(indicators);
+        }
+
+        fn retract(&self, _: TenantId, indicators: &[AbuseIndicator]) {
+            self.retracted.lock().extend_from_slice(indicators);
+        }
+    }
+
+    fn probe(tenant_id: TenantId, dst_ip: IpAddr, dst_port: u16, timestamp: u64) -> EgressFlow {
+        EgressFlow {
+            tenant_id,
+            src_ip: "100.64.0.5".parse().unwrap(),
+            dst_ip,
+            dst_port,
+            server_name: None,
+            failed: true,
+            stratum_handshake: false,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_approved_appeal_retracts_exported_indicators() {
+        let (detector, _, tenant_id) = setup();
+        let sink = Arc::new(RecordingSink::default());
+        let detector = detector.with_intel_sink(sink.clone());
+
+        detector.observe_flow(EgressFlow {
+            stratum_handshake: true,
+            failed: false,
+            ..probe(tenant_id, "203.0.113.50".parse().unwrap(), 3333, now())
+        });
+        let exported = detector.confirm(&tenant_id).unwrap();
+        assert_eq!(*sink.published.lock(), exported);
+
+        let appeal = detector.submit_appeal(&tenant_id, "load test against our own pool").unwrap();
+        detector.start_review(&appeal.appeal_id, "trust-and-safety").unwrap();
+        detector.resolve_appeal(&appeal.appeal_id, true, "verified").unwrap();
+
+        assert_eq!(*sink.retracted.lock(), exported);
+    }
+
+    #[test]
+    fn test_rejected_or_unconfirmed_cases_retract_nothing() {
+        let (detector, _, tenant_id) = setup();
+        let sink = Arc::new(RecordingSink::default());
+        let detector = detector.with_intel_sink(sink.clone());
+
+        detector.observe_flow(EgressFlow {
+            stratum_handshake: true,
+            ..probe(tenant_id, "203.0.113.50".parse().unwrap(), 3333, now())
+        });
+        detector.confirm(&tenant_id).unwrap();
+        let appeal = detector.submit_appeal(&tenant_id, "not us").unwrap();
+        detector.start_review(&appeal.appeal_id, "trust-and-safety").unwrap();
+        detector.resolve_appeal(&appeal.appeal_id, false, "pool traffic confirmed").unwrap();
+        assert!(sink.retracted.lock().is_empty());
+
+        // Approved without ever being confirmed: nothing was exported
+        let (detector, _, tenant_id) = setup();
+        let detector = detector.with_intel_sink(sink.clone());
+        detector.observe_flow(EgressFlow {
+            stratum_handshake: true,
+            ..probe(tenant_id, "203.0.113.50".parse().unwrap(), 3333, now())
+        });
+        let appeal = detector.submit_appeal(&tenant_id, "not us").unwrap();
+        detector.start_review(&appeal.appeal_id, "trust-and-safety").unwrap();
+        detector.resolve_appeal(&appeal.appeal_id, true, "false positive").unwrap();
+        assert!(sink.retracted.lock().is_empty());
+        assert!(!sink.published.lock().is_empty());
+    }
+
+    #[test]
+    fn test_vertical_scan_flags_widest_host() {
+        let (detector, _, tenant_id) = setup();
+        let ts = now();
+        let target: IpAddr = "10.9.9.9".parse().unwrap();
+
+        for port in 1..=120u16 {
+            detector.observe_flow(probe(tenant_id, target, port, ts));
+        }
+
+        let case = detector.case(&tenant_id).unwrap();
+        let vertical = case.evidence.iter().find(|e| e.signal == AbuseSignal::VerticalScan).unwrap();
+        assert!(vertical.detail.ends_with("on 10.9.9.9"), "{}", vertical.detail);
+        assert!(!case.evidence.iter().any(|e| e.signal == AbuseSignal::HorizontalScan));
+    }
+
+    #[test]
+    fn test_window_counts_follow_expiry() {
+        let mut window = EgressWindow::new(3);
+        let tenant_id = TenantId::new_v4();
+        let host: IpAddr = "10.0.0.1".parse().unwrap();
+
+        for (port, ts) in [(1, 100), (2, 100), (3, 200), (3, 250)] {
+            window.push_flow(probe(tenant_id, host, port, ts));
+        }
+        window.push_flow(EgressFlow { failed: false, ..probe(tenant_id, "10.0.0.2".parse().unwrap(), 80, 260) });
+        assert_eq!((window.failed, window.hosts.len()), (4, 2));
+        assert!(window.wide_hosts.contains(&host));
+
+        // Ports 1 and 2 expire; port 3 is still held by two flows
+        window.prune(150);
+        assert_eq!(window.failed, 2);
+        assert_eq!(window.hosts[&host].len(), 1);
+        assert!(window.wide_hosts.is_empty());
+
+        window.prune(255);
+        assert_eq!(window.failed, 0);
+        assert_eq!(window.hosts.keys().collect::<Vec<_>>(), vec![&"10.0.0.2".parse::<IpAddr>().unwrap()]);
+        assert_eq!(window.sources.values().sum::<usize>(), 1);
+
+        window.push_mail(OutboundMail { tenant_id, recipient_domains: vec!["a.example".into(), "b.example".into()], timestamp: 250 });
+        window.push_mail(OutboundMail { tenant_id, recipient_domains: vec!["a.example".into()], timestamp: 300 });
+        window.prune(280);
+        assert_eq!(window.domains.keys().collect::<Vec<_>>(), vec!["a.example"]);
+    }
+
+    #[test]
+    fn test_horizontal_scan_throttles() {
+        let (detector, _, tenant_id) = setup();
+        let ts = now();
+
+        let mut action = AbuseAction::None;
+        for i in 0..600u32 {
+            action = detector.observe_flow(EgressFlow {
+                tenant_id,
+                src_ip: "100.64.0.5".parse().unwrap(),
+                dst_ip: IpAddr::from([10, 1, (i / 256) as u8, (i % 256) as u8]),
+                dst_port: 22,
+                server_name: None,
+                failed: true,
+                stratum_handshake: false,
+                timestamp: ts,
+            });
+        }
+
+        assert_eq!(action, AbuseAction::Throttle);
+        assert!(detector.case(&tenant_id).unwrap().evidence.iter().any(|e| e.signal == AbuseSignal::HorizontalScan));
+    }
+}
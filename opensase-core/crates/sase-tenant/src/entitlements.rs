@@ -2,6 +2,7 @@
 //!
 //! Adapted from BSS-OSS subscription management patterns.
 
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -38,19 +39,84 @@ impl SaseFeature {
 }
 
 /// Subscription tier
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SubscriptionTier {
+    /// Also assumed for entitlements stored before tiers were recorded
+    #[default]
     Starter,
     Business,
     Enterprise,
     Custom,
 }
 
+impl SubscriptionTier {
+    /// Next plan up, used for upgrade hints; `None` at the top
+    pub fn upgrade(&self) -> Option<SubscriptionTier> {
+        match self {
+            Self::Starter => Some(Self::Business),
+            Self::Business => Some(Self::Enterprise),
+            Self::Enterprise | Self::Custom => None,
+        }
+    }
+}
+
 /// Entitlements for a tenant
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Entitlements {
+    /// Plan these entitlements derive from
+    #[serde(default)]
+    pub tier: SubscriptionTier,
     pub features: HashSet<SaseFeature>,
     pub quotas: ResourceQuotas,
+    /// Management API rate limits
+    #[serde(default)]
+    pub api_limits: ApiRateLimits,
+}
+
+/// Management API rate limits granted by the plan
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiRateLimits {
+    /// Sustained request rate
+    pub requests_per_second: u32,
+    /// Requests allowed in a burst above the sustained rate
+    pub burst: u32,
+    /// Requests per rolling minute
+    pub requests_per_minute: u32,
+    /// Requests per day
+    pub requests_per_day: u32,
+}
+
+impl ApiRateLimits {
+    /// Limits for a subscription tier
+    pub fn for_tier(tier: &SubscriptionTier) -> Self {
+        match tier {
+            SubscriptionTier::Starter => Self {
+                requests_per_second: 10,
+                burst: 20,
+                requests_per_minute: 600,
+                requests_per_day: 50_000,
+            },
+            SubscriptionTier::Business => Self {
+                requests_per_second: 50,
+                burst: 100,
+                requests_per_minute: 3_000,
+                requests_per_day: 500_000,
+            },
+            SubscriptionTier::Enterprise | SubscriptionTier::Custom => Self {
+                requests_per_second: 200,
+                burst: 400,
+                requests_per_minute: 12_000,
+                requests_per_day: 5_000_000,
+            },
+        }
+    }
+}
+
+impl Default for ApiRateLimits {
+    /// Limits of the default tier
+    fn default() -> Self {
+        Self::for_tier(&SubscriptionTier::default())
+    }
 }
 
 /// Resource quotas
@@ -77,8 +143,11 @@ impl ResourceQuotas {
 
 impl Entitlements {
     pub fn for_tier(tier: SubscriptionTier) -> Self {
+        let api_limits = ApiRateLimits::for_tier(&tier);
         match tier {
             SubscriptionTier::Starter => Self {
+                tier,
+                api_limits,
                 features: {
                     let mut set = HashSet::new();
                     set.insert(SaseFeature::ZeroTrustAccess);
@@ -95,6 +164,8 @@ impl Entitlements {
                 },
             },
             SubscriptionTier::Business => Self {
+                tier,
+                api_limits,
                 features: {
                     let mut set = HashSet::new();
                     set.insert(SaseFeature::ZeroTrustAccess);
@@ -115,6 +186,8 @@ impl Entitlements {
                 },
             },
             SubscriptionTier::Enterprise | SubscriptionTier::Custom => Self {
+                tier,
+                api_limits,
                 features: SaseFeature::all(),
                 quotas: ResourceQuotas::unlimited(),
             },
@@ -140,6 +213,39 @@ impl Entitlements {
     }
 }
 
+/// Entitlements per tenant, derived from the billing plan
+#[derive(Default)]
+pub struct EntitlementService {
+    tenants: DashMap<String, Entitlements>,
+}
+
+impl EntitlementService {
+    /// Create an empty service
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Apply the standard entitlements for a tenant's plan
+    pub fn assign_plan(&self, tenant_id: &str, tier: SubscriptionTier) {
+        self.tenants.insert(tenant_id.to_string(), Entitlements::for_tier(tier));
+    }
+    
+    /// Apply negotiated entitlements (custom contracts)
+    pub fn set_entitlements(&self, tenant_id: &str, entitlements: Entitlements) {
+        self.tenants.insert(tenant_id.to_string(), entitlements);
+    }
+    
+    /// Entitlements for a tenant, `None` if no plan is assigned
+    pub fn entitlements(&self, tenant_id: &str) -> Option<Entitlements> {
+        self.tenants.get(tenant_id).map(|e| e.clone())
+    }
+    
+    /// Drop a tenant's entitlements
+    pub fn revoke(&self, tenant_id: &str) {
+        self.tenants.remove(tenant_id);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ResourceType { Sites, Users, Policies }
 
@@ -184,4 +290,47 @@ mod tests {
         assert!(ent.check_quota(ResourceType::Sites, 4).is_ok());
         assert!(ent.check_quota(ResourceType::Sites, 5).is_err());
     }
+    
+    #[test]
+    fn test_api_limits_follow_plan() {
+        let service = EntitlementService::new();
+        service.assign_plan("acme", SubscriptionTier::Starter);
+        
+        let starter = service.entitlements("acme").unwrap();
+        assert_eq!(starter.api_limits, ApiRateLimits::for_tier(&SubscriptionTier::Starter));
+        assert_eq!(starter.tier.upgrade(), Some(SubscriptionTier::Business));
+        
+        service.assign_plan("acme", SubscriptionTier::Business);
+        let business = service.entitlements("acme").unwrap();
+        assert!(business.api_limits.requests_per_minute > starter.api_limits.requests_per_minute);
+        assert!(service.entitlements("other").is_none());
+    }
+    
+    #[test]
+    fn test_deserialize_entitlements_without_tier() {
+        // Shape stored before `tier` and `api_limits` existed
+        let stored = r#"{
+            "features": ["ZeroTrustAccess", "SslInspection"],
+            "quotas": {
+                "max_sites": 12,
+                "max_users": 120,
+                "bandwidth_limit_mbps": 200,
+                "policy_limit": 30,
+                "ssl_inspection_gb": 15
+            }
+        }"#;
+        
+        let ent: Entitlements = serde_json::from_str(stored).unwrap();
+        assert_eq!(ent.tier, SubscriptionTier::Starter);
+        assert_eq!(ent.api_limits, ApiRateLimits::for_tier(&SubscriptionTier::Starter));
+        assert!(ent.is_entitled_to(&SaseFeature::SslInspection));
+        assert_eq!(ent.quotas.max_sites, 12);
+        
+        let round_trip: Entitlements =
+            serde_json::from_str(&serde_json::to_string(&ent).unwrap()).unwrap();
+        assert_eq!(round_trip.tier, ent.tier);
+        assert_eq!(round_trip.api_limits, ent.api_limits);
+        assert_eq!(round_trip.features, ent.features);
+        assert_eq!(round_trip.quotas.policy_limit, 30);
+    }
 }
@@ -42,11 +42,13 @@ pub mod lifecycle;
 pub mod entitlements;
 pub mod metering;
 pub mod catalog;
+pub mod abuse;
 
 pub use model::{Tenant, TenantTier, TenantId, TenantRole, ResourceLimits};
 pub use isolation::IsolationEngine;
 pub use limits::QuotaEnforcer;
 pub use identity::IdentityManager;
-pub use entitlements::{SaseFeature, SubscriptionTier, Entitlements};
+pub use entitlements::{SaseFeature, SubscriptionTier, Entitlements, ApiRateLimits, EntitlementService};
 pub use metering::{UsageMetric, UsageRecord, UsageMeter};
 pub use catalog::{ServiceCatalog, SaseServiceOffering, ServiceCart};
+pub use abuse::{AbuseDetector, AbuseAction, AbuseConfig};
@@ -79,6 +79,24 @@ impl TenantRegistry {
         Ok(())
     }
 
+    /// Reactivate a suspended tenant
+    pub fn reactivate(&self, tenant_id: &TenantId) -> Result<(), LifecycleError> {
+        let mut tenants = self.tenants.write();
+        let tenant = tenants.get_mut(tenant_id)
+            .ok_or(LifecycleError::NotFound)?;
+        
+        if tenant.metadata.status != TenantStatus::Suspended {
+            return Err(LifecycleError::InvalidTransition {
+                from: tenant.metadata.status,
+                to: TenantStatus::Active,
+            });
+        }
+        tenant.metadata.status = TenantStatus::Active;
+        tenant.metadata.updated_at = now();
+        
+        Ok(())
+    }
+
     /// Begin offboarding
     pub fn offboard(&self, tenant_id: &TenantId) -> Result<OffboardingState, LifecycleError> {
         let mut tenants = self.tenants.write();
@@ -141,6 +159,14 @@ pub enum LifecycleError {
     NotFound,
     #[error("isolation error: {0}")]
     Isolation(#[from] crate::isolation::IsolationError),
+    /// Status change not allowed from the tenant's current status
+    #[error("cannot move tenant from {from:?} to {to:?}")]
+    InvalidTransition {
+        /// Current status
+        from: TenantStatus,
+        /// Requested status
+        to: TenantStatus,
+    },
 }
 
 fn now() -> u64 {
@@ -175,8 +201,32 @@ mod tests {
         let suspended = registry.get(&tenant.tenant_id).unwrap();
         assert_eq!(suspended.metadata.status, TenantStatus::Suspended);
         
+        // Reactivate
+        registry.reactivate(&tenant.tenant_id).unwrap();
+        assert_eq!(registry.get(&tenant.tenant_id).unwrap().metadata.status, TenantStatus::Active);
+        
         // Delete
         registry.delete(&tenant.tenant_id).unwrap();
         assert_eq!(registry.count(), 0);
     }
+
+    #[test]
+    fn test_reactivate_only_from_suspended() {
+        let registry = TenantRegistry::new(Arc::new(IsolationEngine::new()));
+        let tenant = registry.create("TestCorp", TenantTier::Pro).unwrap();
+
+        assert!(matches!(
+            registry.reactivate(&tenant.tenant_id),
+            Err(LifecycleError::InvalidTransition { from: TenantStatus::Active, to: TenantStatus::Active })
+        ));
+
+        registry.offboard(&tenant.tenant_id).unwrap();
+        assert!(matches!(
+            registry.reactivate(&tenant.tenant_id),
+            Err(LifecycleError::InvalidTransition { from: TenantStatus::Offboarding, .. })
+        ));
+        assert_eq!(registry.get(&tenant.tenant_id).unwrap().metadata.status, TenantStatus::Offboarding);
+
+        assert!(matches!(registry.reactivate(&TenantId::new_v4()), Err(LifecycleError::NotFound)));
+    }
 }
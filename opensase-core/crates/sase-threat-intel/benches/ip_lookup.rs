@@ -0,0 +1,83 @@
+//! IP Lookup Benchmarks
+//!
+//! `lookup_ip` sits on the per-connection path; exact, CIDR and ASN
+//! matches must each stay under 1µs with a realistically sized feed.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sase_threat_intel::{
+    Confidence, Indicator, IocContext, IocType, Severity, ThreatIntelConfig, ThreatIntelService,
+};
+use std::net::{IpAddr, Ipv4Addr};
+
+fn indicator(ioc_type: IocType, value: String) -> Indicator {
+    Indicator {
+        id: value.clone(),
+        ioc_type,
+        value,
+        confidence: Confidence::High,
+        severity: Severity::High,
+        first_seen: chrono::Utc::now(),
+        last_seen: chrono::Utc::now(),
+        expires_at: None,
+        sources: vec![],
+        tags: vec![],
+        context: IocContext::default(),
+        mitre_tactics: vec![],
+        mitre_techniques: vec![],
+        related_iocs: vec![],
+    }
+}
+
+/// 50k host IoCs, 20k /24s, 1k /16s and 5k announced prefixes with
+/// 500 ASN IoCs
+fn populated() -> ThreatIntelService {
+    let service = ThreatIntelService::new(ThreatIntelConfig {
+        max_indicators: 1_000_000,
+        enable_enrichment: false,
+        ..Default::default()
+    });
+
+    for n in 0..50_000u32 {
+        let ip = Ipv4Addr::from(0x0A00_0000 | n.wrapping_mul(2_654_435_761) >> 8);
+        service.ingest(indicator(IocType::IPv4, ip.to_string()));
+    }
+    for n in 0..20_000u32 {
+        let net = Ipv4Addr::from(0x6400_0000 | (n << 8));
+        service.ingest(indicator(IocType::Cidr, format!("{}/24", net)));
+    }
+    for n in 0..1_000u32 {
+        let net = Ipv4Addr::from(0x8000_0000 | (n << 16));
+        service.ingest(indicator(IocType::Cidr, format!("{}/16", net)));
+    }
+    for n in 0..5_000u32 {
+        let net = Ipv4Addr::from(0xC000_0000 | (n << 12));
+        service.add_asn_prefix(&format!("{}/20", net), 64_512 + n);
+        if n % 10 == 0 {
+            service.ingest(indicator(IocType::Asn, format!("AS{}", 64_512 + n)));
+        }
+    }
+
+    service
+}
+
+fn bench_lookup_ip(c: &mut Criterion) {
+    let service = populated();
+    let mut group = c.benchmark_group("lookup_ip");
+
+    let exact = IpAddr::V4(Ipv4Addr::from(0x0A00_0000 | 7u32.wrapping_mul(2_654_435_761) >> 8));
+    let in_cidr24: IpAddr = "100.0.3.77".parse().unwrap();
+    let in_cidr16: IpAddr = "128.5.200.1".parse().unwrap();
+    let in_asn: IpAddr = "192.0.0.9".parse().unwrap();
+    let miss: IpAddr = "203.0.113.9".parse().unwrap();
+
+    group.bench_function("exact", |b| b.iter(|| service.lookup_ip(black_box(exact))));
+    group.bench_function("cidr_24", |b| b.iter(|| service.lookup_ip(black_box(in_cidr24))));
+    group.bench_function("cidr_16", |b| b.iter(|| service.lookup_ip(black_box(in_cidr16))));
+    group.bench_function("asn", |b| b.iter(|| service.lookup_ip(black_box(in_asn))));
+    group.bench_function("miss", |b| b.iter(|| service.lookup_ip(black_box(miss))));
+    group.bench_function("all_matches", |b| b.iter(|| service.lookup_ip_all(black_box(in_cidr24))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup_ip);
+criterion_main!(benches);
@@ -5,7 +5,7 @@
 use crate::{Indicator, IocType, Confidence, Severity, ThreatIntelService, ThreatIntelSnapshot};
 use crate::matching::{IocMatchingEngine, IocMatch, IocCheckRequest};
 use crate::hunting::{ThreatHuntingEngine, HuntingQuery, HuntingResult};
-use crate::sinkhole::{DnsSinkhole, SinkholeSnapshot};
+use crate::sinkhole::{DnsSinkhole, SinkholeSnapshot, VictimRecord};
 use std::sync::Arc;
 
 /// Threat Intelligence API Service
@@ -153,6 +153,22 @@ impl ThreatIntelApi {
         );
     }
     
+    /// Remove domain from sinkhole
+    pub fn remove_from_sinkhole(&self, domain: &str) -> Result<(), ApiError> {
+        if self.sinkhole.unblock(domain) {
+            Ok(())
+        } else {
+            Err(ApiError::NotFound(domain.to_string()))
+        }
+    }
+    
+    /// Clients that resolved sinkholed domains
+    pub fn sinkhole_victims(&self, tenant_id: Option<&str>) -> Vec<VictimRecord> {
+        self.sinkhole.victims().into_iter()
+            .filter(|v| tenant_id.is_none() || v.tenant_id.as_deref() == tenant_id)
+            .collect()
+    }
+    
     /// Get sinkhole statistics
     pub fn sinkhole_stats(&self) -> SinkholeSnapshot {
         self.sinkhole.get_stats()
@@ -347,6 +363,22 @@ pub mod routes {
         api.add_to_sinkhole(request)
     }
     
+    /// DELETE /api/v1/sinkhole/{domain}
+    pub async fn remove_from_sinkhole(
+        api: Arc<ThreatIntelApi>,
+        domain: String,
+    ) -> Result<(), ApiError> {
+        api.remove_from_sinkhole(&domain)
+    }
+    
+    /// GET /api/v1/sinkhole/victims?tenant_id=acme
+    pub async fn sinkhole_victims(
+        api: Arc<ThreatIntelApi>,
+        tenant_id: Option<String>,
+    ) -> Vec<VictimRecord> {
+        api.sinkhole_victims(tenant_id.as_deref())
+    }
+    
     /// GET /api/v1/sinkhole/export?format=rpz
     pub async fn export_sinkhole(
         api: Arc<ThreatIntelApi>,
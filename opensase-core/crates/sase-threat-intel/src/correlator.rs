@@ -3,7 +3,9 @@
 //! Correlates indicators across sources and identifies relationships.
 
 use crate::{Indicator, IocType, IocId, Confidence, Severity};
+use crate::sinkhole::{DnsSinkhole, SinkholeCategory};
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// IoC correlation engine
 pub struct Correlator {
@@ -17,6 +19,8 @@ pub struct Correlator {
     campaigns: dashmap::DashMap<String, CampaignInfo>,
     /// Correlation rules
     rules: Vec<CorrelationRule>,
+    /// DNS sinkhole driven by correlation results
+    sinkhole: Option<Arc<DnsSinkhole>>,
 }
 
 #[derive(Debug, Clone)]
@@ -93,9 +97,62 @@ impl Correlator {
             hash_relations: dashmap::DashMap::new(),
             campaigns: dashmap::DashMap::new(),
             rules: default_correlation_rules(),
+            sinkhole: None,
         }
     }
     
+    /// Allow correlation results to sinkhole domains
+    pub fn with_sinkhole(mut self, sinkhole: Arc<DnsSinkhole>) -> Self {
+        self.sinkhole = Some(sinkhole);
+        self
+    }
+    
+    /// Sinkhole a domain; false when no sinkhole is attached
+    pub fn sinkhole_domain(&self, domain: &str, reason: &str, category: SinkholeCategory) -> bool {
+        let Some(sinkhole) = &self.sinkhole else {
+            return false;
+        };
+        sinkhole.block(domain, reason, category);
+        true
+    }
+    
+    /// Remove a domain from the sinkhole; true if it was sinkholed
+    pub fn unsinkhole_domain(&self, domain: &str) -> bool {
+        self.sinkhole.as_ref().is_some_and(|s| s.unblock(domain))
+    }
+    
+    /// Sinkhole a domain indicator together with the domains correlated to
+    /// it. Returns the domains added.
+    pub fn sinkhole_correlated(&self, indicator: &Indicator, result: &CorrelationResult) -> Vec<String> {
+        if self.sinkhole.is_none() {
+            return Vec::new();
+        }
+        
+        let category = match indicator.context.threat_type {
+            Some(crate::ThreatType::C2) => SinkholeCategory::C2,
+            Some(crate::ThreatType::Phishing) => SinkholeCategory::Phishing,
+            Some(crate::ThreatType::Malware) => SinkholeCategory::Malware,
+            _ => SinkholeCategory::Custom,
+        };
+        let reason = match &result.campaign {
+            Some(campaign) => format!("Correlated with {} (campaign {})", indicator.value, campaign),
+            None => format!("Correlated with {}", indicator.value),
+        };
+        
+        let mut domains = Vec::new();
+        if indicator.ioc_type == IocType::Domain {
+            domains.push(indicator.value.to_lowercase());
+        }
+        domains.extend(result.related_indicators.iter()
+            .filter_map(|id| id.strip_prefix("domain:"))
+            .map(str::to_string));
+        domains.sort();
+        domains.dedup();
+        
+        domains.retain(|domain| self.sinkhole_domain(domain, &reason, category));
+        domains
+    }
+    
     /// Correlate a new indicator
     pub fn correlate(&self, indicator: &Indicator) -> CorrelationResult {
         let mut result = CorrelationResult {
@@ -3,7 +3,10 @@
 //! Push threat intelligence to SASE components.
 
 use crate::{Indicator, IocType, Confidence, Severity};
-use std::collections::HashSet;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
 /// Distributor for pushing intelligence to SASE components
 pub struct Distributor {
@@ -21,6 +24,18 @@ pub struct Distributor {
     min_confidence: Confidence,
     /// HTTP client
     client: reqwest::Client,
+    /// Incremental sink pipelines
+    sinks: Vec<SinkPipeline>,
+    /// Maximum entries per sink push
+    batch_size: usize,
+    /// Serializes flushes so batches are delivered in order
+    flush_lock: tokio::sync::Mutex<()>,
+    /// Sequence number of the last recorded change
+    sequence: std::sync::atomic::AtomicU64,
+    /// Recent changes for pull-based consumers (PoPs catching up after a snapshot)
+    journal: parking_lot::Mutex<VecDeque<IndicatorChange>>,
+    /// Maximum changes kept in the journal
+    journal_capacity: usize,
 }
 
 #[derive(Debug, Default)]
@@ -33,7 +48,7 @@ pub struct DistributorStats {
 }
 
 /// Distribution target
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum DistributionTarget {
     /// XDP first-line defense
     Xdp,
@@ -85,9 +100,189 @@ impl Distributor {
             stats: DistributorStats::default(),
             min_confidence: Confidence::Medium,
             client: reqwest::Client::new(),
+            sinks: Vec::new(),
+            batch_size: 1000,
+            flush_lock: tokio::sync::Mutex::new(()),
+            sequence: std::sync::atomic::AtomicU64::new(0),
+            journal: parking_lot::Mutex::new(VecDeque::new()),
+            journal_capacity: 100_000,
         }
     }
     
+    /// Set how many changes are kept for incremental pulls
+    pub fn with_journal_capacity(mut self, capacity: usize) -> Self {
+        self.journal_capacity = capacity.max(1);
+        self
+    }
+    
+    /// Register an XDP blocklist sink
+    pub fn with_xdp_sink(mut self, name: &str, sink: Arc<dyn XdpBlocklistSink>, filter: SinkFilter) -> Self {
+        self.sinks.push(SinkPipeline::new(name, SinkHandle::Xdp(sink), filter));
+        self
+    }
+    
+    /// Register a URL filter sink
+    pub fn with_url_filter_sink(mut self, name: &str, sink: Arc<dyn UrlFilterSink>, filter: SinkFilter) -> Self {
+        self.sinks.push(SinkPipeline::new(name, SinkHandle::UrlFilter(sink), filter));
+        self
+    }
+    
+    /// Register an IPS rule sink
+    pub fn with_ips_sink(mut self, name: &str, sink: Arc<dyn IpsRuleSink>, filter: SinkFilter) -> Self {
+        self.sinks.push(SinkPipeline::new(name, SinkHandle::Ips(sink), filter));
+        self
+    }
+    
+    /// Set the maximum number of entries per sink push
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    
+    /// Queue an indicator for every sink whose filter accepts it
+    pub fn enqueue(&self, indicator: &Indicator) {
+        self.record(ChangeAction::Upsert, indicator);
+        
+        for sink in &self.sinks {
+            if sink.filter.accepts(indicator) {
+                sink.state.lock().pending.insert(delta_key(indicator), Delta::Upsert(indicator.clone()));
+            }
+        }
+    }
+    
+    /// Queue removal of an indicator from every sink that holds it
+    pub fn retract(&self, indicator: &Indicator) {
+        self.record(ChangeAction::Remove, indicator);
+        let key = delta_key(indicator);
+        
+        for sink in &self.sinks {
+            let mut state = sink.state.lock();
+            if state.delivered.contains(&key) {
+                state.pending.insert(key.clone(), Delta::Remove(indicator.clone()));
+            } else {
+                // Never reached the sink; just drop the queued add
+                state.pending.remove(&key);
+            }
+        }
+    }
+    
+    /// Sequence number of the latest change
+    pub fn sequence(&self) -> u64 {
+        self.sequence.load(std::sync::atomic::Ordering::Acquire)
+    }
+    
+    /// Changes after `since`, oldest first. `None` when `since` has already
+    /// been evicted from the journal and the consumer must re-snapshot.
+    pub fn changes_since(&self, since: u64, limit: usize) -> Option<Vec<IndicatorChange>> {
+        let journal = self.journal.lock();
+        let oldest = journal.front().map(|c| c.sequence).unwrap_or(self.sequence() + 1);
+        if since + 1 < oldest {
+            return None;
+        }
+        
+        Some(journal.iter()
+            .skip_while(|c| c.sequence <= since)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+    
+    fn record(&self, action: ChangeAction, indicator: &Indicator) {
+        let mut journal = self.journal.lock();
+        // Assigned under the journal lock so entries stay in sequence order
+        let sequence = self.sequence.fetch_add(1, std::sync::atomic::Ordering::AcqRel) + 1;
+        journal.push_back(IndicatorChange {
+            sequence,
+            action,
+            indicator: indicator.clone(),
+        });
+        while journal.len() > self.journal_capacity {
+            journal.pop_front();
+        }
+    }
+    
+    /// Push pending changes to all sinks in batches
+    ///
+    /// A failed batch is requeued and the sink is skipped until the next flush.
+    pub async fn flush(&self) -> Vec<SinkFlushReport> {
+        let _guard = self.flush_lock.lock().await;
+        let mut reports = Vec::with_capacity(self.sinks.len());
+        
+        for sink in &self.sinks {
+            let mut report = SinkFlushReport { sink: sink.name.clone(), ..Default::default() };
+            
+            loop {
+                let batch: Vec<(String, Delta)> = {
+                    let mut state = sink.state.lock();
+                    let keys: Vec<String> = state.pending.keys().take(self.batch_size).cloned().collect();
+                    keys.into_iter()
+                        .filter_map(|k| state.pending.remove(&k).map(|d| (k, d)))
+                        .collect()
+                };
+                if batch.is_empty() {
+                    break;
+                }
+                
+                let added: Vec<&Indicator> = batch.iter()
+                    .filter_map(|(_, d)| match d { Delta::Upsert(i) => Some(i), _ => None })
+                    .collect();
+                let removed: Vec<&Indicator> = batch.iter()
+                    .filter_map(|(_, d)| match d { Delta::Remove(i) => Some(i), _ => None })
+                    .collect();
+                
+                match sink.handle.apply(&added, &removed).await {
+                    Ok(()) => {
+                        let mut state = sink.state.lock();
+                        for (key, delta) in &batch {
+                            match delta {
+                                Delta::Upsert(_) => state.delivered.insert(key.clone()),
+                                Delta::Remove(_) => state.delivered.remove(key),
+                            };
+                        }
+                        state.status.delivered += added.len() as u64;
+                        state.status.removed += removed.len() as u64;
+                        state.status.last_success = Some(chrono::Utc::now());
+                        state.status.last_error = None;
+                        
+                        report.delivered += added.len() as u64;
+                        report.removed += removed.len() as u64;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Sink {} push failed: {}", sink.name, e);
+                        let mut state = sink.state.lock();
+                        for (key, delta) in batch {
+                            // Keep any newer change queued while the push was in flight
+                            state.pending.entry(key).or_insert(delta);
+                        }
+                        state.status.failed_batches += 1;
+                        state.status.last_error = Some(e.clone());
+                        
+                        report.error = Some(e);
+                        break;
+                    }
+                }
+            }
+            
+            reports.push(report);
+        }
+        
+        reports
+    }
+    
+    /// Delivery status for every sink
+    pub fn sink_status(&self) -> Vec<SinkDeliveryStatus> {
+        self.sinks.iter().map(|sink| {
+            let state = sink.state.lock();
+            SinkDeliveryStatus {
+                sink: sink.name.clone(),
+                target: Some(sink.handle.target()),
+                pending: state.pending.len(),
+                active: state.delivered.len(),
+                ..state.status.clone()
+            }
+        }).collect()
+    }
+    
     /// Configure XDP endpoint
     pub fn with_xdp(mut self, endpoint: &str) -> Self {
         self.xdp_endpoint = Some(endpoint.to_string());
@@ -469,3 +664,390 @@ pub struct DistributorSnapshot {
     pub ddos_updates: u64,
     pub failed_updates: u64,
 }
+
+// =============================================================================
+// Incremental Sinks
+// =============================================================================
+
+/// XDP first-line blocklist
+#[async_trait]
+pub trait XdpBlocklistSink: Send + Sync {
+    /// Apply an incremental update
+    async fn apply(&self, added: &[BlocklistEntry], removed: &[BlocklistEntry]) -> Result<(), String>;
+}
+
+/// L7 gateway URL/domain filter
+#[async_trait]
+pub trait UrlFilterSink: Send + Sync {
+    /// Apply an incremental update
+    async fn apply(&self, added: &[UrlFilterEntry], removed: &[UrlFilterEntry]) -> Result<(), String>;
+}
+
+/// IPS engine rule set
+#[async_trait]
+pub trait IpsRuleSink: Send + Sync {
+    /// Apply an incremental update
+    async fn apply(&self, added: &[IpsRule], removed: &[IpsRule]) -> Result<(), String>;
+}
+
+/// XDP blocklist entry
+#[derive(Debug, Clone, Serialize)]
+pub struct BlocklistEntry {
+    pub ioc_type: IocType,
+    pub value: String,
+    pub severity: Severity,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<&Indicator> for BlocklistEntry {
+    fn from(i: &Indicator) -> Self {
+        Self {
+            ioc_type: i.ioc_type,
+            value: i.value.clone(),
+            severity: i.severity,
+            expires_at: i.expires_at,
+        }
+    }
+}
+
+/// URL filter entry
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlFilterEntry {
+    pub ioc_type: IocType,
+    pub value: String,
+    pub category: String,
+    pub severity: Severity,
+}
+
+impl From<&Indicator> for UrlFilterEntry {
+    fn from(i: &Indicator) -> Self {
+        let category = match i.context.threat_type {
+            Some(crate::ThreatType::Phishing) => "phishing",
+            Some(crate::ThreatType::Malware) => "malware",
+            Some(crate::ThreatType::C2) => "c2",
+            _ => "threat",
+        };
+        
+        Self {
+            ioc_type: i.ioc_type,
+            value: i.value.clone(),
+            category: category.to_string(),
+            severity: i.severity,
+        }
+    }
+}
+
+/// IPS match rule
+#[derive(Debug, Clone, Serialize)]
+pub struct IpsRule {
+    pub id: String,
+    pub ioc_type: IocType,
+    pub value: String,
+    pub severity: Severity,
+    pub mitre_techniques: Vec<String>,
+}
+
+impl From<&Indicator> for IpsRule {
+    fn from(i: &Indicator) -> Self {
+        Self {
+            id: i.id.clone(),
+            ioc_type: i.ioc_type,
+            value: i.value.clone(),
+            severity: i.severity,
+            mitre_techniques: i.mitre_techniques.clone(),
+        }
+    }
+}
+
+/// Per-sink admission filter
+#[derive(Debug, Clone)]
+pub struct SinkFilter {
+    pub min_confidence: Confidence,
+    pub min_severity: Severity,
+    /// Accepted IoC types
+    pub ioc_types: Vec<IocType>,
+}
+
+impl SinkFilter {
+    /// IPs and networks
+    pub fn xdp() -> Self {
+        Self {
+            min_confidence: Confidence::High,
+            min_severity: Severity::Medium,
+            ioc_types: vec![IocType::IPv4, IocType::IPv6, IocType::Cidr],
+        }
+    }
+    
+    /// Domains and URLs
+    pub fn url_filter() -> Self {
+        Self {
+            min_confidence: Confidence::Medium,
+            min_severity: Severity::Low,
+            ioc_types: vec![IocType::Domain, IocType::Url],
+        }
+    }
+    
+    /// File hashes and TLS fingerprints
+    pub fn ips() -> Self {
+        Self {
+            min_confidence: Confidence::Medium,
+            min_severity: Severity::Low,
+            ioc_types: vec![
+                IocType::FileHashMd5, IocType::FileHashSha1, IocType::FileHashSha256,
+                IocType::Ja3Hash, IocType::JarmHash, IocType::SslCertHash,
+            ],
+        }
+    }
+    
+    pub fn accepts(&self, indicator: &Indicator) -> bool {
+        indicator.confidence >= self.min_confidence
+            && indicator.severity >= self.min_severity
+            && self.ioc_types.contains(&indicator.ioc_type)
+    }
+}
+
+/// Journaled change to the distributable indicator set
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct IndicatorChange {
+    pub sequence: u64,
+    pub action: ChangeAction,
+    pub indicator: Indicator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Upsert,
+    Remove,
+}
+
+/// Delivery status for one sink
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SinkDeliveryStatus {
+    pub sink: String,
+    pub target: Option<DistributionTarget>,
+    /// Entries pushed
+    pub delivered: u64,
+    /// Removals pushed
+    pub removed: u64,
+    pub failed_batches: u64,
+    /// Changes waiting to be pushed
+    pub pending: usize,
+    /// Entries currently held by the sink
+    pub active: usize,
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Outcome of one flush for one sink
+#[derive(Debug, Clone, Default)]
+pub struct SinkFlushReport {
+    pub sink: String,
+    pub delivered: u64,
+    pub removed: u64,
+    pub error: Option<String>,
+}
+
+struct SinkPipeline {
+    name: String,
+    handle: SinkHandle,
+    filter: SinkFilter,
+    state: parking_lot::Mutex<SinkState>,
+}
+
+impl SinkPipeline {
+    fn new(name: &str, handle: SinkHandle, filter: SinkFilter) -> Self {
+        Self {
+            name: name.to_string(),
+            handle,
+            filter,
+            state: parking_lot::Mutex::new(SinkState::default()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SinkState {
+    /// Latest queued change per indicator
+    pending: HashMap<String, Delta>,
+    /// Indicators the sink currently holds
+    delivered: HashSet<String>,
+    status: SinkDeliveryStatus,
+}
+
+enum Delta {
+    Upsert(Indicator),
+    Remove(Indicator),
+}
+
+enum SinkHandle {
+    Xdp(Arc<dyn XdpBlocklistSink>),
+    UrlFilter(Arc<dyn UrlFilterSink>),
+    Ips(Arc<dyn IpsRuleSink>),
+}
+
+impl SinkHandle {
+    fn target(&self) -> DistributionTarget {
+        match self {
+            Self::Xdp(_) => DistributionTarget::Xdp,
+            Self::UrlFilter(_) => DistributionTarget::L7Gateway,
+            Self::Ips(_) => DistributionTarget::IpsEngine,
+        }
+    }
+    
+    async fn apply(&self, added: &[&Indicator], removed: &[&Indicator]) -> Result<(), String> {
+        fn convert<'a, T: From<&'a Indicator>>(items: &[&'a Indicator]) -> Vec<T> {
+            items.iter().map(|i| T::from(*i)).collect()
+        }
+        
+        match self {
+            Self::Xdp(sink) => sink.apply(&convert(added), &convert(removed)).await,
+            Self::UrlFilter(sink) => sink.apply(&convert(added), &convert(removed)).await,
+            Self::Ips(sink) => sink.apply(&convert(added), &convert(removed)).await,
+        }
+    }
+}
+
+fn delta_key(indicator: &Indicator) -> String {
+    format!("{:?}:{}", indicator.ioc_type, indicator.value.to_lowercase())
+}
+
+/// HTTP sink posting `{ "add": [...], "remove": [...] }` to a component endpoint
+pub struct HttpSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpSink {
+    /// XDP blocklist at `{endpoint}/blocklist/batch`
+    pub fn xdp(endpoint: &str) -> Self {
+        Self::new(format!("{}/blocklist/batch", endpoint))
+    }
+    
+    /// L7 gateway at `{endpoint}/url-filter/batch`
+    pub fn url_filter(endpoint: &str) -> Self {
+        Self::new(format!("{}/url-filter/batch", endpoint))
+    }
+    
+    /// IPS engine at `{endpoint}/rules/batch`
+    pub fn ips(endpoint: &str) -> Self {
+        Self::new(format!("{}/rules/batch", endpoint))
+    }
+    
+    fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+    
+    async fn post<T: Serialize + Sync>(&self, added: &[T], removed: &[T]) -> Result<(), String> {
+        let response = self.client.post(&self.url)
+            .json(&serde_json::json!({ "add": added, "remove": removed }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("{} returned {}", self.url, response.status()))
+        }
+    }
+}
+
+#[async_trait]
+impl XdpBlocklistSink for HttpSink {
+    async fn apply(&self, added: &[BlocklistEntry], removed: &[BlocklistEntry]) -> Result<(), String> {
+        self.post(added, removed).await
+    }
+}
+
+#[async_trait]
+impl UrlFilterSink for HttpSink {
+    async fn apply(&self, added: &[UrlFilterEntry], removed: &[UrlFilterEntry]) -> Result<(), String> {
+        self.post(added, removed).await
+    }
+}
+
+#[async_trait]
+impl IpsRuleSink for HttpSink {
+    async fn apply(&self, added: &[IpsRule], removed: &[IpsRule]) -> Result<(), String> {
+        self.post(added, removed).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[derive(Default)]
+    struct MemorySink {
+        entries: parking_lot::Mutex<HashSet<String>>,
+        fail: std::sync::atomic::AtomicBool,
+    }
+    
+    #[async_trait]
+    impl XdpBlocklistSink for MemorySink {
+        async fn apply(&self, added: &[BlocklistEntry], removed: &[BlocklistEntry]) -> Result<(), String> {
+            if self.fail.load(std::sync::atomic::Ordering::Relaxed) {
+                return Err("unavailable".into());
+            }
+            let mut entries = self.entries.lock();
+            entries.extend(added.iter().map(|e| e.value.clone()));
+            for e in removed {
+                entries.remove(&e.value);
+            }
+            Ok(())
+        }
+    }
+    
+    fn ip(value: &str, confidence: Confidence) -> Indicator {
+        Indicator {
+            id: value.to_string(),
+            ioc_type: IocType::IPv4,
+            value: value.to_string(),
+            confidence,
+            severity: Severity::High,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at: None,
+            sources: vec![],
+            tags: vec![],
+            context: crate::IocContext::default(),
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            related_iocs: vec![],
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_incremental_push_and_retract() {
+        let sink = Arc::new(MemorySink::default());
+        let distributor = Distributor::new()
+            .with_xdp_sink("pop-1", sink.clone(), SinkFilter::xdp())
+            .with_batch_size(1);
+        
+        distributor.enqueue(&ip("198.51.100.1", Confidence::High));
+        distributor.enqueue(&ip("198.51.100.2", Confidence::High));
+        distributor.enqueue(&ip("198.51.100.3", Confidence::Low));
+        
+        let reports = distributor.flush().await;
+        assert_eq!(reports[0].delivered, 2);
+        assert_eq!(sink.entries.lock().len(), 2);
+        
+        // Failed pushes stay queued
+        sink.fail.store(true, std::sync::atomic::Ordering::Relaxed);
+        distributor.retract(&ip("198.51.100.1", Confidence::High));
+        assert!(distributor.flush().await[0].error.is_some());
+        assert_eq!(distributor.sink_status()[0].pending, 1);
+        
+        sink.fail.store(false, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(distributor.flush().await[0].removed, 1);
+        
+        let status = &distributor.sink_status()[0];
+        assert_eq!(status.active, 1);
+        assert_eq!(status.failed_batches, 1);
+        assert!(!sink.entries.lock().contains("198.51.100.1"));
+    }
+}
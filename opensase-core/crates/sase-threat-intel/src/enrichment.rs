@@ -1,17 +1,38 @@
 //! IoC Enrichment
 //!
-//! Context enrichment for indicators using external services.
+//! Context enrichment for indicators using external services. Each source is
+//! an [`EnrichmentProvider`]; the [`Enricher`] fans an indicator out to every
+//! provider that supports its type, merges the partial results, and caches
+//! them.
 
-use crate::{Indicator, IocType, IocContext, GeoLocation, WhoisData, DnsRecord};
+use crate::{Indicator, IocType, GeoLocation, WhoisData, DnsRecord};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// A source of enrichment data (GeoIP database, WHOIS, passive DNS,
+/// commercial reputation APIs)
+#[async_trait]
+pub trait EnrichmentProvider: Send + Sync {
+    /// Provider name for logging
+    fn name(&self) -> &str;
+    
+    /// Whether the provider can enrich this IoC type
+    fn supports(&self, ioc_type: IocType) -> bool;
+    
+    /// Return whatever fields the provider knows; unset fields are merged
+    /// from other providers
+    async fn enrich(&self, indicator: &Indicator) -> Result<EnrichmentResult, String>;
+}
 
 /// Enrichment engine for adding context to indicators
 pub struct Enricher {
-    /// GeoIP database
-    geoip: Option<GeoIpDb>,
-    /// Enable passive DNS
-    enable_pdns: bool,
-    /// Enable WHOIS
-    enable_whois: bool,
+    /// Registered providers
+    providers: Vec<Arc<dyn EnrichmentProvider>>,
+    /// Bounds concurrent enrichments across all callers
+    limiter: Arc<tokio::sync::Semaphore>,
+    max_concurrent: usize,
+    /// How long results are reused
+    cache_ttl: chrono::Duration,
     /// Cache for enrichment results
     cache: dashmap::DashMap<String, EnrichmentCache>,
 }
@@ -34,6 +55,35 @@ pub struct EnrichmentResult {
     pub additional_context: std::collections::HashMap<String, String>,
 }
 
+impl EnrichmentResult {
+    /// Fold another provider's result into this one
+    fn merge(&mut self, other: EnrichmentResult) {
+        if self.geo.is_none() {
+            self.geo = other.geo;
+        }
+        if self.whois.is_none() {
+            self.whois = other.whois;
+        }
+        for record in other.dns_records {
+            if !self.dns_records.iter().any(|r| r.record_type == record.record_type && r.value == record.value) {
+                self.dns_records.push(record);
+            }
+        }
+        // Keep the most malicious verdict
+        self.reputation_score = match (self.reputation_score, other.reputation_score) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.first_seen_global = match (self.first_seen_global, other.first_seen_global) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.related_samples.extend(other.related_samples);
+        self.ssl_certificates.extend(other.ssl_certificates);
+        self.additional_context.extend(other.additional_context);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SslCertInfo {
     pub fingerprint: String,
@@ -43,20 +93,46 @@ pub struct SslCertInfo {
     pub valid_until: String,
 }
 
-struct GeoIpDb {
-    // In production: MaxMind GeoLite2 database
-}
-
 impl Enricher {
     pub fn new() -> Self {
         Self {
-            geoip: None,
-            enable_pdns: true,
-            enable_whois: true,
+            providers: Vec::new(),
+            limiter: Arc::new(tokio::sync::Semaphore::new(8)),
+            max_concurrent: 8,
+            cache_ttl: chrono::Duration::hours(24),
             cache: dashmap::DashMap::new(),
         }
     }
     
+    /// Register a provider
+    pub fn with_provider(mut self, provider: Arc<dyn EnrichmentProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+    
+    /// Limit concurrent enrichments
+    pub fn with_concurrency(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self.limiter = Arc::new(tokio::sync::Semaphore::new(self.max_concurrent));
+        self
+    }
+    
+    /// Set how long results are cached
+    pub fn with_cache_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+    
+    /// Maximum concurrent enrichments
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+    
+    /// Whether any provider is registered
+    pub fn has_providers(&self) -> bool {
+        !self.providers.is_empty()
+    }
+    
     /// Enrich an indicator with additional context
     pub async fn enrich(&self, indicator: &mut Indicator) -> EnrichmentResult {
         let cache_key = format!("{}:{}", indicator.ioc_type as u8, &indicator.value);
@@ -69,39 +145,22 @@ impl Enricher {
             }
         }
         
-        let mut result = EnrichmentResult::default();
+        let _permit = self.limiter.acquire().await;
         
-        match indicator.ioc_type {
-            IocType::IPv4 | IocType::IPv6 => {
-                result.geo = self.lookup_geo(&indicator.value).await;
-                if self.enable_pdns {
-                    result.dns_records = self.lookup_pdns_ip(&indicator.value).await;
-                }
-                result.reputation_score = self.lookup_ip_reputation(&indicator.value).await;
-            }
-            IocType::Domain => {
-                if self.enable_whois {
-                    result.whois = self.lookup_whois(&indicator.value).await;
-                }
-                if self.enable_pdns {
-                    result.dns_records = self.lookup_pdns_domain(&indicator.value).await;
-                }
-                result.reputation_score = self.lookup_domain_reputation(&indicator.value).await;
-            }
-            IocType::Url => {
-                // Extract domain and enrich
-                if let Some(domain) = extract_domain(&indicator.value) {
-                    if self.enable_whois {
-                        result.whois = self.lookup_whois(&domain).await;
-                    }
-                    result.reputation_score = self.lookup_url_reputation(&indicator.value).await;
-                }
-            }
-            IocType::FileHashMd5 | IocType::FileHashSha1 | IocType::FileHashSha256 => {
-                result.related_samples = self.lookup_hash_relations(&indicator.value).await;
-                result.reputation_score = self.lookup_hash_reputation(&indicator.value).await;
+        let lookups = self.providers.iter()
+            .filter(|p| p.supports(indicator.ioc_type))
+            .map(|p| {
+                let provider = p.clone();
+                let snapshot = indicator.clone();
+                async move { (provider.name().to_string(), provider.enrich(&snapshot).await) }
+            });
+        
+        let mut result = EnrichmentResult::default();
+        for (provider, outcome) in futures::future::join_all(lookups).await {
+            match outcome {
+                Ok(partial) => result.merge(partial),
+                Err(e) => tracing::debug!("Enrichment provider {} failed for {}: {}", provider, indicator.value, e),
             }
-            _ => {}
         }
         
         // Apply to indicator
@@ -110,7 +169,7 @@ impl Enricher {
         // Cache result
         let cache_entry = EnrichmentCache {
             data: result.clone(),
-            expires_at: chrono::Utc::now() + chrono::Duration::hours(24),
+            expires_at: chrono::Utc::now() + self.cache_ttl,
         };
         self.cache.insert(cache_key, cache_entry);
         
@@ -126,7 +185,9 @@ impl Enricher {
             indicator.context.whois = Some(whois.clone());
         }
         
-        indicator.context.dns_records = result.dns_records.clone();
+        if !result.dns_records.is_empty() {
+            indicator.context.dns_records = result.dns_records.clone();
+        }
         
         // Adjust severity based on reputation
         if let Some(score) = result.reputation_score {
@@ -138,72 +199,300 @@ impl Enricher {
         }
     }
     
-    async fn lookup_geo(&self, ip: &str) -> Option<GeoLocation> {
-        // In production: MaxMind GeoLite2 lookup
-        // Placeholder response
+    /// Drop expired cache entries
+    pub fn purge_cache(&self) {
+        let now = chrono::Utc::now();
+        self.cache.retain(|_, entry| entry.expires_at > now);
+    }
+}
+
+impl Default for Enricher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn extract_domain(url: &str) -> Option<String> {
+    url.trim_start_matches("http://")
+        .trim_start_matches("https://")
+        .split('/')
+        .next()
+        .map(|s| s.split(':').next().unwrap_or(s).to_string())
+}
+
+/// Domain to query for a domain or URL indicator
+fn indicator_domain(indicator: &Indicator) -> Option<String> {
+    match indicator.ioc_type {
+        IocType::Domain => Some(indicator.value.trim_start_matches("*.").to_lowercase()),
+        IocType::Url => extract_domain(&indicator.value.to_lowercase()),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// Built-in Providers
+// =============================================================================
+
+/// MaxMind GeoIP2/GeoLite2 City and ASN databases
+pub struct MaxMindGeoIp {
+    city: maxminddb::Reader<Vec<u8>>,
+    asn: Option<maxminddb::Reader<Vec<u8>>>,
+}
+
+impl MaxMindGeoIp {
+    /// Open the City database and, optionally, the ASN database
+    pub fn open(
+        city_db: impl AsRef<std::path::Path>,
+        asn_db: Option<impl AsRef<std::path::Path>>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            city: maxminddb::Reader::open_readfile(city_db).map_err(|e| e.to_string())?,
+            asn: asn_db
+                .map(|p| maxminddb::Reader::open_readfile(p).map_err(|e| e.to_string()))
+                .transpose()?,
+        })
+    }
+    
+    /// Look up an address
+    pub fn lookup(&self, ip: std::net::IpAddr) -> Option<GeoLocation> {
+        let city: maxminddb::geoip2::City = self.city.lookup(ip).ok()?;
+        let country = city.country.as_ref();
+        let asn: Option<maxminddb::geoip2::Asn> = self.asn.as_ref().and_then(|r| r.lookup(ip).ok());
+        
         Some(GeoLocation {
-            country: "Unknown".to_string(),
-            country_code: "XX".to_string(),
-            city: None,
-            asn: None,
-            as_org: None,
+            country: country
+                .and_then(|c| c.names.as_ref())
+                .and_then(|n| n.get("en"))
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            country_code: country
+                .and_then(|c| c.iso_code)
+                .unwrap_or("XX")
+                .to_string(),
+            city: city.city
+                .and_then(|c| c.names)
+                .and_then(|n| n.get("en").map(|s| s.to_string())),
+            asn: asn.as_ref().and_then(|a| a.autonomous_system_number),
+            as_org: asn.and_then(|a| a.autonomous_system_organization.map(String::from)),
         })
     }
+}
+
+#[async_trait]
+impl EnrichmentProvider for MaxMindGeoIp {
+    fn name(&self) -> &str {
+        "maxmind"
+    }
     
-    async fn lookup_whois(&self, domain: &str) -> Option<WhoisData> {
-        // In production: WHOIS lookup via rdap or whois protocol
-        None
+    fn supports(&self, ioc_type: IocType) -> bool {
+        matches!(ioc_type, IocType::IPv4 | IocType::IPv6)
     }
     
-    async fn lookup_pdns_ip(&self, ip: &str) -> Vec<DnsRecord> {
-        // Passive DNS lookup for IP
-        Vec::new()
+    async fn enrich(&self, indicator: &Indicator) -> Result<EnrichmentResult, String> {
+        let ip = indicator.value.parse().map_err(|_| "not an IP address".to_string())?;
+        Ok(EnrichmentResult {
+            geo: self.lookup(ip),
+            ..Default::default()
+        })
+    }
+}
+
+/// WHOIS over RDAP
+pub struct RdapWhois {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl RdapWhois {
+    /// Use the rdap.org bootstrap redirector
+    pub fn new() -> Self {
+        Self::with_base_url("https://rdap.org")
     }
     
-    async fn lookup_pdns_domain(&self, domain: &str) -> Vec<DnsRecord> {
-        // Passive DNS lookup for domain
-        Vec::new()
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for RdapWhois {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EnrichmentProvider for RdapWhois {
+    fn name(&self) -> &str {
+        "rdap"
     }
     
-    async fn lookup_ip_reputation(&self, ip: &str) -> Option<i32> {
-        // IP reputation score (-100 to 100)
-        // In production: Query reputation services
-        None
+    fn supports(&self, ioc_type: IocType) -> bool {
+        matches!(ioc_type, IocType::Domain | IocType::Url)
     }
     
-    async fn lookup_domain_reputation(&self, domain: &str) -> Option<i32> {
-        // Domain reputation score
-        None
+    async fn enrich(&self, indicator: &Indicator) -> Result<EnrichmentResult, String> {
+        let domain = indicator_domain(indicator).ok_or("no domain")?;
+        
+        let response = self.client.get(format!("{}/domain/{}", self.base_url, domain))
+            .header("Accept", "application/rdap+json")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("RDAP returned {}", response.status()));
+        }
+        
+        let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+        Ok(EnrichmentResult {
+            whois: Some(parse_rdap(&body)),
+            ..Default::default()
+        })
     }
+}
+
+/// Map an RDAP domain response onto WHOIS fields
+fn parse_rdap(body: &serde_json::Value) -> WhoisData {
+    let event = |action: &str| body["events"].as_array()
+        .and_then(|events| events.iter().find(|e| e["eventAction"] == action))
+        .and_then(|e| e["eventDate"].as_str())
+        .map(String::from);
+    
+    let entity = |role: &str| body["entities"].as_array()
+        .and_then(|entities| entities.iter().find(|e| {
+            e["roles"].as_array().map(|r| r.iter().any(|x| x == role)).unwrap_or(false)
+        }));
+    
+    // jCard: ["vcard", [["fn", {}, "text", "Name"], ["adr", {"cc": "US"}, ...]]]
+    let vcard_field = |entity: Option<&serde_json::Value>, field: &str| entity
+        .and_then(|e| e["vcardArray"][1].as_array())
+        .and_then(|props| props.iter().find(|p| p[0] == field))
+        .cloned();
     
-    async fn lookup_url_reputation(&self, url: &str) -> Option<i32> {
-        // URL reputation score
-        None
+    let registrant = entity("registrant");
+    
+    WhoisData {
+        registrar: vcard_field(entity("registrar"), "fn").and_then(|p| p[3].as_str().map(String::from)),
+        created_date: event("registration"),
+        updated_date: event("last changed"),
+        expires_date: event("expiration"),
+        registrant_org: vcard_field(registrant, "org")
+            .or_else(|| vcard_field(registrant, "fn"))
+            .and_then(|p| p[3].as_str().map(String::from)),
+        registrant_country: vcard_field(registrant, "adr")
+            .and_then(|p| p[1]["cc"].as_str().map(String::from)),
+    }
+}
+
+/// Passive DNS over the Common Output Format (CIRCL, Farsight-compatible)
+pub struct PassiveDns {
+    base_url: String,
+    credentials: Option<(String, String)>,
+    client: reqwest::Client,
+}
+
+impl PassiveDns {
+    /// `base_url` is the query endpoint, e.g. `https://www.circl.lu/pdns/query`
+    pub fn new(base_url: &str, credentials: Option<(String, String)>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            credentials,
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(15))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl EnrichmentProvider for PassiveDns {
+    fn name(&self) -> &str {
+        "pdns"
     }
     
-    async fn lookup_hash_reputation(&self, hash: &str) -> Option<i32> {
-        // File hash reputation score
-        None
+    fn supports(&self, ioc_type: IocType) -> bool {
+        matches!(ioc_type, IocType::IPv4 | IocType::IPv6 | IocType::Domain | IocType::Url)
     }
     
-    async fn lookup_hash_relations(&self, hash: &str) -> Vec<String> {
-        // Related malware samples
-        Vec::new()
+    async fn enrich(&self, indicator: &Indicator) -> Result<EnrichmentResult, String> {
+        let query = match indicator.ioc_type {
+            IocType::IPv4 | IocType::IPv6 => indicator.value.clone(),
+            _ => indicator_domain(indicator).ok_or("no domain")?,
+        };
+        
+        let mut request = self.client.get(format!("{}/{}", self.base_url, query));
+        if let Some((user, pass)) = &self.credentials {
+            request = request.basic_auth(user, Some(pass));
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("passive DNS returned {}", response.status()));
+        }
+        
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        Ok(EnrichmentResult {
+            dns_records: parse_cof(&body, &query),
+            ..Default::default()
+        })
     }
 }
 
-impl Default for Enricher {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Parse newline-delimited COF records, reporting the side opposite the query
+fn parse_cof(body: &str, query: &str) -> Vec<DnsRecord> {
+    body.lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|r| {
+            let rrname = r["rrname"].as_str()?.trim_end_matches('.');
+            let rdata = match &r["rdata"] {
+                serde_json::Value::Array(values) => values.first()?.as_str()?,
+                v => v.as_str()?,
+            };
+            let value = if rrname.eq_ignore_ascii_case(query) { rdata } else { rrname };
+            
+            Some(DnsRecord {
+                record_type: r["rrtype"].as_str().unwrap_or("A").to_string(),
+                value: value.trim_end_matches('.').to_string(),
+                ttl: r["ttl"].as_u64().unwrap_or(0) as u32,
+                first_seen: r["time_first"].as_i64()
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                    .unwrap_or_else(chrono::Utc::now),
+            })
+        })
+        .collect()
 }
 
-fn extract_domain(url: &str) -> Option<String> {
-    url.trim_start_matches("http://")
-        .trim_start_matches("https://")
-        .split('/')
-        .next()
-        .map(|s| s.split(':').next().unwrap_or(s).to_string())
+#[async_trait]
+impl EnrichmentProvider for sources::AbuseIpDbClient {
+    fn name(&self) -> &str {
+        "abuseipdb"
+    }
+    
+    fn supports(&self, ioc_type: IocType) -> bool {
+        matches!(ioc_type, IocType::IPv4 | IocType::IPv6)
+    }
+    
+    async fn enrich(&self, indicator: &Indicator) -> Result<EnrichmentResult, String> {
+        let report = self.check_ip(&indicator.value).await.ok_or("AbuseIPDB lookup failed")?;
+        
+        let mut result = EnrichmentResult {
+            // Abuse confidence 0..100 maps onto reputation 0..-100
+            reputation_score: Some(-(report.data.abuse_confidence_score.min(100) as i32)),
+            ..Default::default()
+        };
+        if let Some(isp) = report.data.isp {
+            result.additional_context.insert("isp".to_string(), isp);
+        }
+        if let Some(usage) = report.data.usage_type {
+            result.additional_context.insert("usage_type".to_string(), usage);
+        }
+        Ok(result)
+    }
 }
 
 /// External enrichment sources
@@ -338,3 +627,87 @@ pub mod sources {
         pub total_reports: u32,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Confidence, IocContext, Severity, ThreatIntelConfig, ThreatIntelService};
+    
+    struct StaticGeo;
+    
+    #[async_trait]
+    impl EnrichmentProvider for StaticGeo {
+        fn name(&self) -> &str { "static" }
+        
+        fn supports(&self, ioc_type: IocType) -> bool {
+            ioc_type == IocType::IPv4
+        }
+        
+        async fn enrich(&self, _indicator: &Indicator) -> Result<EnrichmentResult, String> {
+            Ok(EnrichmentResult {
+                geo: Some(GeoLocation {
+                    country: "Netherlands".into(),
+                    country_code: "NL".into(),
+                    city: None,
+                    asn: Some(64496),
+                    as_org: None,
+                }),
+                reputation_score: Some(-80),
+                ..Default::default()
+            })
+        }
+    }
+    
+    #[test]
+    fn test_parse_cof() {
+        let body = concat!(
+            r#"{"rrname":"evil.example.","rrtype":"A","rdata":"203.0.113.7","time_first":1700000000}"#, "\n",
+            r#"{"rrname":"cdn.evil.example","rrtype":"CNAME","rdata":["evil.example."]}"#, "\n",
+        );
+        
+        let records = parse_cof(body, "evil.example");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value, "203.0.113.7");
+        assert_eq!(records[1].value, "cdn.evil.example");
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_enriches_high_severity() {
+        let service = Arc::new(
+            ThreatIntelService::new(ThreatIntelConfig::default())
+                .with_enricher(Enricher::new().with_provider(Arc::new(StaticGeo)).with_concurrency(2)),
+        );
+        service.spawn_enrichment().unwrap();
+        assert!(service.spawn_enrichment().is_none());
+        
+        let ioc = |value: &str, severity| Indicator {
+            id: value.to_string(),
+            ioc_type: IocType::IPv4,
+            value: value.to_string(),
+            confidence: Confidence::High,
+            severity,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at: None,
+            sources: vec![],
+            tags: vec![],
+            context: IocContext::default(),
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            related_iocs: vec![],
+        };
+        service.ingest(ioc("203.0.113.7", Severity::High));
+        service.ingest(ioc("203.0.113.8", Severity::Low));
+        
+        for _ in 0..50 {
+            if service.lookup(IocType::IPv4, "203.0.113.7").unwrap().context.geo_location.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        
+        let enriched = service.lookup(IocType::IPv4, "203.0.113.7").unwrap();
+        assert_eq!(enriched.context.geo_location.unwrap().country_code, "NL");
+        assert!(service.lookup(IocType::IPv4, "203.0.113.8").unwrap().context.geo_location.is_none());
+    }
+}
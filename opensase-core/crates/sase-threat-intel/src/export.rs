@@ -0,0 +1,283 @@
+//! STIX 2.1 Export
+//!
+//! Serialize our curated indicator set into STIX 2.1 bundles for sharing
+//! with downstream SOCs and partner organizations.
+
+use crate::{Indicator, IocType, ThreatType};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Namespace for deterministic STIX identifiers, so re-exports of the same
+/// indicator keep the same id across bundles
+const STIX_NAMESPACE: uuid::Uuid = uuid::Uuid::from_u128(0x6f3b_52a4_8b1e_4c9d_9a3f_0e5d_c2b7_a981);
+
+/// TLP 2.0 marking definitions (STIX 2.1 §7.2.1.4 well-known ids),
+/// ordered least to most restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Tlp {
+    Clear,
+    Green,
+    Amber,
+    Red,
+}
+
+impl Tlp {
+    pub fn marking_ref(&self) -> &'static str {
+        match self {
+            Tlp::Clear => "marking-definition--613f2e26-407d-48c7-9eca-b8e91df99dc9",
+            Tlp::Green => "marking-definition--34098fce-860f-48ae-8e50-ebd3cc5e41da",
+            Tlp::Amber => "marking-definition--f88d31f6-486f-44da-b317-01333bde0b82",
+            Tlp::Red => "marking-definition--5e57c739-391a-4eb3-b6be-7d15ca92d5ed",
+        }
+    }
+    
+    /// Parse a `tlp:` tag; TLP 1.0 `white` maps to `Clear` and
+    /// `amber+strict` to `Amber`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let (prefix, level) = tag.split_once(':')?;
+        if !prefix.eq_ignore_ascii_case("tlp") {
+            return None;
+        }
+        match level.trim_matches('"').to_ascii_lowercase().as_str() {
+            "clear" | "white" => Some(Tlp::Clear),
+            "green" => Some(Tlp::Green),
+            "amber" | "amber+strict" => Some(Tlp::Amber),
+            "red" => Some(Tlp::Red),
+            _ => None,
+        }
+    }
+    
+    /// Most restrictive TLP among a set of tags
+    pub fn from_tags<'a>(tags: impl IntoIterator<Item = &'a String>) -> Option<Self> {
+        tags.into_iter().filter_map(|t| Self::from_tag(t)).max()
+    }
+}
+
+/// STIX 2.1 bundle of exported objects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportBundle {
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    pub id: String,
+    pub objects: Vec<Value>,
+}
+
+/// Converts indicators into STIX 2.1 objects
+#[derive(Debug, Clone)]
+pub struct StixExporter {
+    /// Producer identity referenced by `created_by_ref`
+    identity_id: String,
+    identity_name: String,
+    tlp: Tlp,
+}
+
+impl StixExporter {
+    pub fn new(organization: &str) -> Self {
+        Self {
+            identity_id: stix_id("identity", organization),
+            identity_name: organization.to_string(),
+            tlp: Tlp::Amber,
+        }
+    }
+    
+    pub fn with_tlp(mut self, tlp: Tlp) -> Self {
+        self.tlp = tlp;
+        self
+    }
+    
+    pub fn identity_id(&self) -> &str {
+        &self.identity_id
+    }
+    
+    /// Producer identity object
+    pub fn identity(&self) -> Value {
+        json!({
+            "type": "identity",
+            "spec_version": "2.1",
+            "id": self.identity_id,
+            "created": "2024-01-01T00:00:00.000Z",
+            "modified": "2024-01-01T00:00:00.000Z",
+            "name": self.identity_name,
+            "identity_class": "organization",
+        })
+    }
+    
+    /// STIX indicator for an IoC, `None` for types without a pattern mapping
+    pub fn indicator(&self, indicator: &Indicator) -> Option<Value> {
+        let pattern = stix_pattern(indicator.ioc_type, &indicator.value)?;
+        
+        let mut obj = json!({
+            "type": "indicator",
+            "spec_version": "2.1",
+            "id": indicator_stix_id(indicator),
+            "created_by_ref": self.identity_id,
+            "created": stix_time(&indicator.first_seen),
+            "modified": stix_time(&indicator.last_seen.max(indicator.first_seen)),
+            "name": indicator.value,
+            "indicator_types": [indicator_type(indicator.context.threat_type)],
+            "pattern": pattern,
+            "pattern_type": "stix",
+            "pattern_version": "2.1",
+            "valid_from": stix_time(&indicator.first_seen),
+            "confidence": indicator.confidence as u32,
+            "object_marking_refs": [self.marking_for(indicator).marking_ref()],
+        });
+        
+        let fields = obj.as_object_mut()?;
+        if let Some(expires) = &indicator.expires_at {
+            fields.insert("valid_until".into(), json!(stix_time(expires)));
+        }
+        if let Some(description) = &indicator.context.description {
+            fields.insert("description".into(), json!(description));
+        }
+        if !indicator.tags.is_empty() {
+            fields.insert("labels".into(), json!(indicator.tags));
+        }
+        if !indicator.mitre_tactics.is_empty() {
+            let phases: Vec<Value> = indicator.mitre_tactics.iter()
+                .map(|t| json!({ "kill_chain_name": "mitre-attack", "phase_name": t }))
+                .collect();
+            fields.insert("kill_chain_phases".into(), json!(phases));
+        }
+        if !indicator.mitre_techniques.is_empty() {
+            let refs: Vec<Value> = indicator.mitre_techniques.iter()
+                .map(|t| json!({
+                    "source_name": "mitre-attack",
+                    "external_id": t,
+                    "url": format!("https://attack.mitre.org/techniques/{}/", t.replace('.', "/")),
+                }))
+                .collect();
+            fields.insert("external_references".into(), json!(refs));
+        }
+        
+        Some(obj)
+    }
+    
+    /// Exporter TLP, raised to the indicator's own TLP tag if stricter
+    fn marking_for(&self, indicator: &Indicator) -> Tlp {
+        Tlp::from_tags(&indicator.tags).map_or(self.tlp, |tlp| tlp.max(self.tlp))
+    }
+    
+    /// Indicator plus related SDOs (malware family) and relationships
+    pub fn objects_for(&self, indicator: &Indicator) -> Vec<Value> {
+        let Some(stix_indicator) = self.indicator(indicator) else {
+            return Vec::new();
+        };
+        let mut objects = vec![stix_indicator];
+        
+        if let Some(family) = &indicator.context.malware_family {
+            let malware_id = stix_id("malware", &family.to_lowercase());
+            let created = stix_time(&indicator.first_seen);
+            objects.push(json!({
+                "type": "malware",
+                "spec_version": "2.1",
+                "id": malware_id,
+                "created_by_ref": self.identity_id,
+                "created": created,
+                "modified": created,
+                "name": family,
+                "is_family": true,
+                "object_marking_refs": [self.tlp.marking_ref()],
+            }));
+            objects.push(self.relationship(&indicator_stix_id(indicator), "indicates", &malware_id, &created));
+        }
+        
+        objects
+    }
+    
+    /// Bundle with the producer identity and every exportable indicator
+    pub fn bundle(&self, indicators: &[Indicator]) -> ExportBundle {
+        let mut seen = HashSet::new();
+        let mut objects = vec![self.identity()];
+        
+        for indicator in indicators {
+            for obj in self.objects_for(indicator) {
+                let id = obj["id"].as_str().unwrap_or_default().to_string();
+                if seen.insert(id) {
+                    objects.push(obj);
+                }
+            }
+        }
+        
+        ExportBundle {
+            bundle_type: "bundle".to_string(),
+            id: format!("bundle--{}", uuid::Uuid::new_v4()),
+            objects,
+        }
+    }
+    
+    fn relationship(&self, source: &str, relationship_type: &str, target: &str, created: &str) -> Value {
+        json!({
+            "type": "relationship",
+            "spec_version": "2.1",
+            "id": stix_id("relationship", &format!("{}|{}|{}", source, relationship_type, target)),
+            "created_by_ref": self.identity_id,
+            "created": created,
+            "modified": created,
+            "relationship_type": relationship_type,
+            "source_ref": source,
+            "target_ref": target,
+        })
+    }
+}
+
+/// Stable STIX id for an indicator; ids imported from STIX are kept
+pub fn indicator_stix_id(indicator: &Indicator) -> String {
+    if indicator.id.starts_with("indicator--") {
+        return indicator.id.clone();
+    }
+    stix_id("indicator", &format!("{:?}:{}", indicator.ioc_type, indicator.value))
+}
+
+/// STIX patterning expression for an IoC
+pub fn stix_pattern(ioc_type: IocType, value: &str) -> Option<String> {
+    let v = escape_pattern_value(value);
+    let pattern = match ioc_type {
+        IocType::IPv4 => format!("[ipv4-addr:value = '{}']", v),
+        IocType::IPv6 => format!("[ipv6-addr:value = '{}']", v),
+        IocType::Cidr if value.contains(':') => format!("[ipv6-addr:value = '{}']", v),
+        IocType::Cidr => format!("[ipv4-addr:value = '{}']", v),
+        IocType::Domain => format!("[domain-name:value = '{}']", v.trim_start_matches("*.")),
+        IocType::Url => format!("[url:value = '{}']", v),
+        IocType::FileHashMd5 => format!("[file:hashes.MD5 = '{}']", v),
+        IocType::FileHashSha1 => format!("[file:hashes.'SHA-1' = '{}']", v),
+        IocType::FileHashSha256 => format!("[file:hashes.'SHA-256' = '{}']", v),
+        IocType::Email => format!("[email-addr:value = '{}']", v),
+        IocType::SslCertHash => format!("[x509-certificate:hashes.'SHA-1' = '{}']", v),
+        IocType::UserAgent => format!(
+            "[network-traffic:extensions.'http-request-ext'.request_header.'User-Agent' = '{}']", v),
+        IocType::Asn => {
+            let number: u32 = value.trim_start_matches(['A', 'S', 'a', 's']).parse().ok()?;
+            format!("[autonomous-system:number = {}]", number)
+        }
+        IocType::RegistryKey => format!("[windows-registry-key:key = '{}']", v),
+        IocType::Mutex => format!("[mutex:name = '{}']", v),
+        IocType::Cve | IocType::JarmHash | IocType::Ja3Hash => return None,
+    };
+    Some(pattern)
+}
+
+fn escape_pattern_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn indicator_type(threat_type: Option<ThreatType>) -> &'static str {
+    match threat_type {
+        Some(ThreatType::Proxy) | Some(ThreatType::Tor) | Some(ThreatType::Vpn) => "anonymization",
+        Some(ThreatType::Scanner) => "anomalous-activity",
+        Some(ThreatType::Apt) => "attribution",
+        Some(ThreatType::Exploit) => "compromised",
+        Some(_) => "malicious-activity",
+        None => "unknown",
+    }
+}
+
+fn stix_id(object_type: &str, name: &str) -> String {
+    format!("{}--{}", object_type, uuid::Uuid::new_v5(&STIX_NAMESPACE, format!("{}:{}", object_type, name).as_bytes()))
+}
+
+/// RFC 3339 timestamp with millisecond precision, as STIX requires
+pub fn stix_time(time: &chrono::DateTime<chrono::Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
@@ -0,0 +1,867 @@
+//! MISP Integration
+//!
+//! Client for the MISP REST API and MISP manifest-based feeds, including
+//! attribute-to-IoC mapping and sighting push-back.
+
+use crate::{
+    Confidence, FeedConfig, Indicator, IntelSource, IocContext, IocType, Severity, ThreatType,
+};
+use crate::export::Tlp;
+use super::FeedError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+// =============================================================================
+// MISP Data Model
+// =============================================================================
+
+/// Wrapper used by the MISP API around a single event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MispEventWrapper {
+    #[serde(rename = "Event")]
+    pub event: MispEvent,
+}
+
+/// MISP event
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispEvent {
+    #[serde(default)]
+    pub uuid: String,
+    #[serde(default)]
+    pub info: String,
+    /// 1 = High, 2 = Medium, 3 = Low, 4 = Undefined
+    #[serde(default)]
+    pub threat_level_id: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub published: bool,
+    #[serde(rename = "Orgc", default)]
+    pub orgc: Option<MispOrg>,
+    #[serde(rename = "Attribute", default)]
+    pub attributes: Vec<MispAttribute>,
+    #[serde(rename = "Object", default)]
+    pub objects: Vec<MispObject>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<MispTag>,
+    #[serde(rename = "Galaxy", default)]
+    pub galaxies: Vec<MispGalaxy>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispOrg {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub uuid: Option<String>,
+}
+
+/// MISP attribute
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispAttribute {
+    #[serde(default)]
+    pub uuid: String,
+    #[serde(rename = "type")]
+    pub attr_type: String,
+    #[serde(default)]
+    pub category: String,
+    pub value: String,
+    #[serde(default)]
+    pub to_ids: bool,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub first_seen: Option<String>,
+    #[serde(default)]
+    pub last_seen: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<MispTag>,
+    #[serde(rename = "Galaxy", default)]
+    pub galaxies: Vec<MispGalaxy>,
+}
+
+/// MISP object (groups related attributes, e.g. a `file` object)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispObject {
+    #[serde(default)]
+    pub uuid: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "Attribute", default)]
+    pub attributes: Vec<MispAttribute>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispTag {
+    pub name: String,
+}
+
+/// MISP galaxy (e.g. mitre-attack-pattern, threat-actor)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispGalaxy {
+    #[serde(rename = "type", default)]
+    pub galaxy_type: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "GalaxyCluster", default)]
+    pub clusters: Vec<MispGalaxyCluster>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MispGalaxyCluster {
+    #[serde(default)]
+    pub value: String,
+    #[serde(default)]
+    pub tag_name: String,
+    #[serde(default)]
+    pub meta: HashMap<String, serde_json::Value>,
+}
+
+/// Entry of a MISP feed `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MispManifestEntry {
+    #[serde(default)]
+    pub info: String,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub threat_level_id: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<MispTag>,
+}
+
+/// Sighting pushed back to MISP when a sensor hits an indicator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MispSighting {
+    /// Attribute UUID (preferred) - falls back to value matching when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// 0 = sighting, 1 = false positive, 2 = expiration
+    #[serde(rename = "type")]
+    pub sighting_type: String,
+    pub source: String,
+    /// Unix timestamp of the hit
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SightingType {
+    Sighting,
+    FalsePositive,
+    Expiration,
+}
+
+impl SightingType {
+    fn as_misp(&self) -> &'static str {
+        match self {
+            Self::Sighting => "0",
+            Self::FalsePositive => "1",
+            Self::Expiration => "2",
+        }
+    }
+}
+
+// =============================================================================
+// MISP Client
+// =============================================================================
+
+/// MISP client configuration
+#[derive(Debug, Clone)]
+pub struct MispConfig {
+    /// Only pull attributes flagged `to_ids`
+    pub to_ids_only: bool,
+    /// Only pull published events
+    pub published_only: bool,
+    /// Look-back window for the first pull
+    pub initial_lookback_days: u32,
+    /// Page size for restSearch; at least 1
+    pub page_size: u32,
+    /// Source name reported with sightings
+    pub sighting_source: String,
+    /// Most restrictive TLP accepted; stricter attributes are dropped
+    pub max_tlp: Tlp,
+}
+
+impl Default for MispConfig {
+    fn default() -> Self {
+        Self {
+            to_ids_only: true,
+            published_only: true,
+            initial_lookback_days: 30,
+            page_size: 100,
+            sighting_source: "opensase".to_string(),
+            max_tlp: Tlp::Red,
+        }
+    }
+}
+
+/// MISP REST API / manifest feed client
+pub struct MispClient {
+    base_url: String,
+    api_key: Option<String>,
+    config: MispConfig,
+    client: reqwest::Client,
+    /// Last successful pull, used as the `timestamp` filter for the next one
+    last_pull: parking_lot::Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+}
+
+impl MispClient {
+    pub fn new(base_url: &str, api_key: Option<&str>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.map(|k| k.to_string()),
+            config: MispConfig::default(),
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(60))
+                .build()
+                .unwrap(),
+            last_pull: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Build a client from a feed configuration
+    pub fn from_feed(config: &FeedConfig) -> Self {
+        Self::new(&config.url, config.api_key.as_deref())
+    }
+
+    pub fn with_config(mut self, config: MispConfig) -> Result<Self, FeedError> {
+        // A zero limit returns full pages of nothing and never ends the pull
+        if config.page_size == 0 {
+            return Err(FeedError::InvalidConfig("MISP page_size must be at least 1".to_string()));
+        }
+        self.config = config;
+        Ok(self)
+    }
+
+    /// Whether the configured URL points at a manifest-based feed
+    pub fn is_manifest_feed(&self) -> bool {
+        self.base_url.ends_with("manifest.json")
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let mut request = self.client.request(method, url)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json");
+
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", api_key.clone());
+        }
+
+        request
+    }
+
+    /// Pull events changed since the last pull via `/events/restSearch`
+    pub async fn search_events(&self) -> Result<Vec<MispEvent>, FeedError> {
+        // Anything modified while we page through is picked up next time
+        let started = chrono::Utc::now();
+        let since = self.last_pull.lock().unwrap_or_else(|| {
+            started - chrono::Duration::days(self.config.initial_lookback_days as i64)
+        });
+
+        let mut events = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let body = serde_json::json!({
+                "returnFormat": "json",
+                "timestamp": since.timestamp().to_string(),
+                "published": self.config.published_only,
+                "to_ids": self.config.to_ids_only,
+                "includeGalaxy": true,
+                "limit": self.config.page_size,
+                "page": page,
+            });
+
+            let response = self.request(reqwest::Method::POST, &format!("{}/events/restSearch", self.base_url))
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| FeedError::Network(e.to_string()))?;
+
+            if !response.status().is_success() {
+                return Err(FeedError::HttpError(response.status().as_u16()));
+            }
+
+            let json: serde_json::Value = response.json().await
+                .map_err(|e| FeedError::Parse(e.to_string()))?;
+
+            let batch = parse_event_list(&json)?;
+            let count = batch.len();
+            events.extend(batch);
+
+            if count < self.config.page_size as usize {
+                break;
+            }
+            page += 1;
+        }
+
+        *self.last_pull.lock() = Some(started);
+        info!("MISP restSearch returned {} events since {}", events.len(), since);
+
+        Ok(events)
+    }
+
+    /// Fetch a manifest-based feed: `manifest.json` followed by each changed event
+    pub async fn fetch_manifest_feed(&self) -> Result<Vec<MispEvent>, FeedError> {
+        let root = self.base_url.trim_end_matches("manifest.json").trim_end_matches('/');
+        let started = chrono::Utc::now();
+
+        let response = self.request(reqwest::Method::GET, &self.base_url)
+            .send()
+            .await
+            .map_err(|e| FeedError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(FeedError::HttpError(response.status().as_u16()));
+        }
+
+        let manifest: HashMap<String, MispManifestEntry> = response.json().await
+            .map_err(|e| FeedError::Parse(e.to_string()))?;
+
+        let since = *self.last_pull.lock();
+        let mut events = Vec::new();
+
+        for (uuid, entry) in &manifest {
+            let changed = match (since, parse_unix(entry.timestamp.as_deref())) {
+                (Some(since), Some(ts)) => ts > since,
+                _ => true,
+            };
+            if !changed {
+                continue;
+            }
+
+            match self.fetch_feed_event(root, uuid).await {
+                Ok(event) => events.push(event),
+                Err(e) => warn!("Failed to fetch MISP feed event {}: {}", uuid, e),
+            }
+        }
+
+        *self.last_pull.lock() = Some(started);
+        debug!("MISP manifest feed {} yielded {} changed events", self.base_url, events.len());
+
+        Ok(events)
+    }
+
+    async fn fetch_feed_event(&self, root: &str, uuid: &str) -> Result<MispEvent, FeedError> {
+        let response = self.request(reqwest::Method::GET, &format!("{}/{}.json", root, uuid))
+            .send()
+            .await
+            .map_err(|e| FeedError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(FeedError::HttpError(response.status().as_u16()));
+        }
+
+        let wrapper: MispEventWrapper = response.json().await
+            .map_err(|e| FeedError::Parse(e.to_string()))?;
+
+        Ok(wrapper.event)
+    }
+
+    /// Pull events (REST or manifest mode) and map them to indicators
+    pub async fn pull_indicators(&self, feed: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
+        let events = if self.is_manifest_feed() {
+            self.fetch_manifest_feed().await?
+        } else {
+            self.search_events().await?
+        };
+
+        let max_tlp = self.config.max_tlp;
+        Ok(events.iter()
+            .flat_map(|event| event_to_indicators(event, feed, self.config.to_ids_only))
+            .filter(|indicator| !matches!(Tlp::from_tags(&indicator.tags), Some(tlp) if tlp > max_tlp))
+            .collect())
+    }
+
+    /// Push a sighting back to MISP
+    pub async fn add_sighting(&self, sighting: &MispSighting) -> Result<(), FeedError> {
+        if self.is_manifest_feed() {
+            // Static feeds have no API to report into
+            return Ok(());
+        }
+
+        let response = self.request(reqwest::Method::POST, &format!("{}/sightings/add", self.base_url))
+            .json(sighting)
+            .send()
+            .await
+            .map_err(|e| FeedError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(FeedError::HttpError(response.status().as_u16()));
+        }
+
+        Ok(())
+    }
+
+    /// Build a sighting for an indicator that was hit by one of our sensors
+    pub fn sighting_for(&self, indicator: &Indicator, sighting_type: SightingType) -> MispSighting {
+        // MISP-sourced indicators keep the attribute UUID as their id
+        let uuid = uuid::Uuid::parse_str(&indicator.id).ok().map(|u| u.to_string());
+        let values = if uuid.is_none() { vec![indicator.value.clone()] } else { Vec::new() };
+
+        MispSighting {
+            uuid,
+            values,
+            sighting_type: sighting_type.as_misp().to_string(),
+            source: self.config.sighting_source.clone(),
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+// =============================================================================
+// Attribute Mapping
+// =============================================================================
+
+/// Parse a restSearch / index response into events
+pub fn parse_event_list(json: &serde_json::Value) -> Result<Vec<MispEvent>, FeedError> {
+    let items = json.get("response")
+        .and_then(|r| r.as_array())
+        .or_else(|| json.as_array());
+
+    let Some(items) = items else {
+        return Ok(Vec::new());
+    };
+
+    items.iter()
+        .map(|item| {
+            let event = item.get("Event").unwrap_or(item);
+            serde_json::from_value::<MispEvent>(event.clone())
+                .map_err(|e| FeedError::Parse(e.to_string()))
+        })
+        .collect()
+}
+
+/// Map a MISP attribute type (and value) to IoC (type, value) pairs.
+///
+/// Composite types such as `domain|ip` or `filename|sha256` yield every
+/// component that maps to an IoC type.
+pub fn map_attribute(attr_type: &str, value: &str) -> Vec<(IocType, String)> {
+    if let Some((left_type, right_type)) = attr_type.split_once('|') {
+        let (left, right) = value.split_once('|').unwrap_or((value, ""));
+        let mut mapped = Vec::new();
+        for (t, v) in [(left_type, left), (right_type, right)] {
+            // `ip-dst|port` style composites: the port carries no IoC value
+            if t == "port" || v.is_empty() {
+                continue;
+            }
+            mapped.extend(map_attribute(t, v));
+        }
+        return mapped;
+    }
+
+    let value = value.trim();
+    let ioc_type = match attr_type {
+        "ip-src" | "ip-dst" | "ip" => {
+            if value.contains('/') {
+                IocType::Cidr
+            } else {
+                match value.parse::<std::net::IpAddr>() {
+                    Ok(std::net::IpAddr::V4(_)) => IocType::IPv4,
+                    Ok(std::net::IpAddr::V6(_)) => IocType::IPv6,
+                    Err(_) => return Vec::new(),
+                }
+            }
+        }
+        "domain" | "hostname" => IocType::Domain,
+        "url" | "uri" | "link" => IocType::Url,
+        "md5" if value.len() == 32 => IocType::FileHashMd5,
+        "sha1" => IocType::FileHashSha1,
+        "sha256" => IocType::FileHashSha256,
+        "email" | "email-src" | "email-dst" | "email-reply-to" => IocType::Email,
+        "vulnerability" => IocType::Cve,
+        "ja3-fingerprint-md5" => IocType::Ja3Hash,
+        "jarm-fingerprint" => IocType::JarmHash,
+        "x509-fingerprint-md5" | "x509-fingerprint-sha1" | "x509-fingerprint-sha256" => IocType::SslCertHash,
+        "user-agent" => IocType::UserAgent,
+        "AS" => IocType::Asn,
+        "regkey" => IocType::RegistryKey,
+        "mutex" => IocType::Mutex,
+        _ => return Vec::new(),
+    };
+
+    vec![(ioc_type, value.to_string())]
+}
+
+/// Map MISP `threat_level_id` to severity
+pub fn map_threat_level(level: Option<&str>) -> Severity {
+    match level {
+        Some("1") => Severity::High,
+        Some("2") => Severity::Medium,
+        Some("3") => Severity::Low,
+        _ => Severity::Info,
+    }
+}
+
+/// MITRE ATT&CK and actor context extracted from tags and galaxy clusters
+#[derive(Debug, Clone, Default)]
+pub struct MispThreatContext {
+    pub techniques: Vec<String>,
+    pub kill_chain_phases: Vec<String>,
+    pub threat_actor: Option<String>,
+    pub malware_family: Option<String>,
+    pub threat_type: Option<ThreatType>,
+    /// Most restrictive TLP seen on the event or attribute
+    pub tlp: Option<Tlp>,
+    pub tags: Vec<String>,
+}
+
+impl MispThreatContext {
+    /// Extract context from a set of tags and galaxies
+    pub fn extract(tags: &[MispTag], galaxies: &[MispGalaxy]) -> Self {
+        let mut ctx = Self::default();
+
+        for galaxy in galaxies {
+            for cluster in &galaxy.clusters {
+                ctx.apply_cluster(&galaxy.galaxy_type, cluster);
+            }
+        }
+
+        for tag in tags {
+            ctx.apply_tag(&tag.name);
+        }
+
+        ctx
+    }
+
+    /// Merge another context (e.g. attribute-level over event-level)
+    pub fn merge(&mut self, other: &MispThreatContext) {
+        for t in &other.techniques {
+            push_unique(&mut self.techniques, t);
+        }
+        for p in &other.kill_chain_phases {
+            push_unique(&mut self.kill_chain_phases, p);
+        }
+        for t in &other.tags {
+            push_unique(&mut self.tags, t);
+        }
+        if other.threat_actor.is_some() {
+            self.threat_actor = other.threat_actor.clone();
+        }
+        if other.malware_family.is_some() {
+            self.malware_family = other.malware_family.clone();
+        }
+        if other.threat_type.is_some() {
+            self.threat_type = other.threat_type;
+        }
+        // An attribute can tighten the event's TLP but never loosen it
+        self.tlp = self.tlp.max(other.tlp);
+    }
+
+    fn apply_cluster(&mut self, galaxy_type: &str, cluster: &MispGalaxyCluster) {
+        if galaxy_type.starts_with("mitre-") {
+            // external_id carries the ATT&CK ID, value looks like "Name - T1566.001"
+            let ids = cluster.meta.get("external_id")
+                .and_then(|v| v.as_array())
+                .map(|ids| ids.iter().filter_map(|i| i.as_str()).map(|s| s.to_string()).collect::<Vec<_>>())
+                .unwrap_or_default();
+
+            for id in ids.iter().chain(extract_technique_ids(&cluster.value).iter()) {
+                if id.starts_with('T') {
+                    push_unique(&mut self.techniques, id);
+                }
+            }
+
+            if let Some(phases) = cluster.meta.get("kill_chain").and_then(|v| v.as_array()) {
+                for phase in phases.iter().filter_map(|p| p.as_str()) {
+                    // e.g. "mitre-attack:initial-access"
+                    let phase = phase.rsplit(':').next().unwrap_or(phase);
+                    push_unique(&mut self.kill_chain_phases, phase);
+                }
+            }
+        }
+
+        match galaxy_type {
+            "threat-actor" | "mitre-intrusion-set" => {
+                self.threat_actor = Some(strip_attack_id(&cluster.value));
+                self.threat_type.get_or_insert(ThreatType::Apt);
+            }
+            "mitre-malware" | "malpedia" | "mitre-tool" => {
+                self.malware_family = Some(strip_attack_id(&cluster.value));
+                self.threat_type.get_or_insert(ThreatType::Malware);
+            }
+            "ransomware" => {
+                self.malware_family = Some(cluster.value.clone());
+                self.threat_type = Some(ThreatType::Ransomware);
+            }
+            "botnet" => {
+                self.malware_family = Some(cluster.value.clone());
+                self.threat_type.get_or_insert(ThreatType::Botnet);
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_tag(&mut self, tag: &str) {
+        // Galaxy tags appear as plain tags in feeds exported without galaxies
+        if let Some(rest) = tag.strip_prefix("misp-galaxy:") {
+            if let Some((galaxy_type, value)) = rest.split_once('=') {
+                let cluster = MispGalaxyCluster {
+                    value: value.trim_matches('"').to_string(),
+                    tag_name: tag.to_string(),
+                    meta: HashMap::new(),
+                };
+                self.apply_cluster(galaxy_type, &cluster);
+            }
+            return;
+        }
+
+        if let Some(phase) = tag.strip_prefix("kill-chain:") {
+            push_unique(&mut self.kill_chain_phases, &phase.trim_matches('"').to_lowercase());
+            return;
+        }
+
+        if let Some(tlp) = Tlp::from_tag(tag) {
+            self.tlp = self.tlp.max(Some(tlp));
+            push_unique(&mut self.tags, tag);
+            return;
+        }
+        if tag.starts_with("PAP:") {
+            push_unique(&mut self.tags, tag);
+            return;
+        }
+
+        // Taxonomy tags like `malware_classification:malware-category="Ransomware"`
+        let leaf = tag.rsplit(['=', ':']).next().unwrap_or(tag).trim_matches('"').to_lowercase();
+        if let Some(threat_type) = crate::stix::label_to_threat_type(&leaf) {
+            self.threat_type.get_or_insert(threat_type);
+        }
+        push_unique(&mut self.tags, tag);
+    }
+}
+
+/// Convert a MISP event into indicators
+pub fn event_to_indicators(event: &MispEvent, feed: &FeedConfig, to_ids_only: bool) -> Vec<Indicator> {
+    let event_ctx = MispThreatContext::extract(&event.tags, &event.galaxies);
+    let severity = map_threat_level(event.threat_level_id.as_deref());
+
+    let object_attrs = event.objects.iter().flat_map(|o| o.attributes.iter());
+
+    event.attributes.iter()
+        .chain(object_attrs)
+        .filter(|attr| attr.to_ids || !to_ids_only)
+        .flat_map(|attr| attribute_to_indicators(attr, event, &event_ctx, severity, feed))
+        .collect()
+}
+
+fn attribute_to_indicators(
+    attr: &MispAttribute,
+    event: &MispEvent,
+    event_ctx: &MispThreatContext,
+    severity: Severity,
+    feed: &FeedConfig,
+) -> Vec<Indicator> {
+    let mapped = map_attribute(&attr.attr_type, &attr.value);
+    if mapped.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ctx = event_ctx.clone();
+    ctx.merge(&MispThreatContext::extract(&attr.tags, &attr.galaxies));
+
+    let now = chrono::Utc::now();
+    let first_seen = parse_misp_time(attr.first_seen.as_deref())
+        .or_else(|| parse_unix(attr.timestamp.as_deref()))
+        .unwrap_or(now);
+    let last_seen = parse_misp_time(attr.last_seen.as_deref()).unwrap_or(first_seen);
+
+    // Non-IDS attributes are context, not detection material
+    let confidence = if attr.to_ids { feed.default_confidence } else { Confidence::Low };
+
+    let mut tags = feed.tags.clone();
+    for tag in &ctx.tags {
+        push_unique(&mut tags, tag);
+    }
+
+    let reference_url = if event.uuid.is_empty() {
+        None
+    } else {
+        Some(format!("{}/events/view/{}", feed.url.trim_end_matches('/'), event.uuid))
+    };
+
+    let composite = mapped.len() > 1;
+
+    mapped.into_iter()
+        .enumerate()
+        .map(|(i, (ioc_type, value))| Indicator {
+            id: match (attr.uuid.is_empty(), composite) {
+                (true, _) => uuid::Uuid::new_v4().to_string(),
+                (false, false) => attr.uuid.clone(),
+                (false, true) => format!("{}-{}", attr.uuid, i),
+            },
+            ioc_type,
+            value,
+            confidence,
+            severity,
+            first_seen,
+            last_seen,
+            expires_at: None,
+            sources: vec![IntelSource {
+                name: feed.name.clone(),
+                feed_id: feed.id.clone(),
+                reliability: feed.reliability,
+                timestamp: now,
+                reference_url: reference_url.clone(),
+            }],
+            tags: tags.clone(),
+            context: IocContext {
+                threat_type: ctx.threat_type,
+                malware_family: ctx.malware_family.clone(),
+                threat_actor: ctx.threat_actor.clone(),
+                description: Some(match &attr.comment {
+                    Some(comment) if !comment.is_empty() => format!("{} - {}", event.info, comment),
+                    _ => event.info.clone(),
+                }),
+                kill_chain_phases: ctx.kill_chain_phases.clone(),
+                ..Default::default()
+            },
+            mitre_tactics: Vec::new(),
+            mitre_techniques: ctx.techniques.clone(),
+            related_iocs: Vec::new(),
+        })
+        .collect()
+}
+
+fn extract_technique_ids(text: &str) -> Vec<String> {
+    static RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| regex::Regex::new(r"\bT\d{4}(?:\.\d{3})?\b").unwrap());
+    re.find_iter(text).map(|m| m.as_str().to_string()).collect()
+}
+
+/// "APT28 - G0007" -> "APT28"
+fn strip_attack_id(value: &str) -> String {
+    match value.rsplit_once(" - ") {
+        Some((name, id)) if id.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+            && id[1..].chars().all(|c| c.is_ascii_digit() || c == '.') => name.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn parse_unix(ts: Option<&str>) -> Option<chrono::DateTime<chrono::Utc>> {
+    let secs = ts?.parse::<i64>().ok()?;
+    chrono::DateTime::from_timestamp(secs, 0)
+}
+
+fn parse_misp_time(ts: Option<&str>) -> Option<chrono::DateTime<chrono::Utc>> {
+    let ts = ts?;
+    chrono::DateTime::parse_from_rfc3339(ts)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .ok()
+        .or_else(|| parse_unix(Some(ts)))
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed() -> FeedConfig {
+        FeedConfig {
+            id: "misp-test".to_string(),
+            name: "MISP".to_string(),
+            feed_type: crate::FeedType::Misp,
+            url: "https://misp.example.org".to_string(),
+            api_key: None,
+            poll_interval: std::time::Duration::from_secs(900),
+            enabled: true,
+            reliability: crate::Reliability::B,
+            default_confidence: Confidence::High,
+            ioc_types: vec![],
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn test_composite_attribute_mapping() {
+        let mapped = map_attribute("domain|ip", "evil.example|2001:db8::1");
+        assert_eq!(mapped, vec![
+            (IocType::Domain, "evil.example".to_string()),
+            (IocType::IPv6, "2001:db8::1".to_string()),
+        ]);
+
+        let mapped = map_attribute("ip-dst|port", "198.51.100.7|443");
+        assert_eq!(mapped, vec![(IocType::IPv4, "198.51.100.7".to_string())]);
+
+        assert!(map_attribute("text", "anything").is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_hashes_are_not_md5() {
+        let md5 = "44d88612fea8a8f36de82e1278abb02f";
+        assert_eq!(map_attribute("md5", md5), vec![(IocType::FileHashMd5, md5.to_string())]);
+        assert!(map_attribute("md5", "44d88612").is_empty());
+        assert!(map_attribute("impfuzzy", md5).is_empty());
+        assert!(map_attribute("filename|impfuzzy", &format!("evil.exe|{}", md5)).is_empty());
+    }
+
+    #[test]
+    fn test_zero_page_size_rejected() {
+        let config = MispConfig { page_size: 0, ..MispConfig::default() };
+        assert!(matches!(
+            MispClient::from_feed(&feed()).with_config(config),
+            Err(FeedError::InvalidConfig(_))
+        ));
+        assert!(MispClient::from_feed(&feed()).with_config(MispConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_event_galaxy_to_mitre() {
+        let json = serde_json::json!({
+            "uuid": "5e1f1b2c-0000-4000-8000-000000000001",
+            "info": "Phishing wave",
+            "threat_level_id": "1",
+            "Tag": [{ "name": "misp-galaxy:threat-actor=\"APT28\"" }],
+            "Galaxy": [{
+                "type": "mitre-attack-pattern",
+                "GalaxyCluster": [{
+                    "value": "Spearphishing Attachment - T1566.001",
+                    "meta": { "kill_chain": ["mitre-attack:initial-access"] }
+                }]
+            }],
+            "Attribute": [
+                { "uuid": "5e1f1b2c-0000-4000-8000-000000000002", "type": "domain", "value": "login-update.example", "to_ids": true },
+                { "type": "comment", "value": "ignored", "to_ids": false }
+            ]
+        });
+        let event: MispEvent = serde_json::from_value(json).unwrap();
+
+        let indicators = event_to_indicators(&event, &feed(), true);
+        assert_eq!(indicators.len(), 1);
+
+        let ioc = &indicators[0];
+        assert_eq!(ioc.ioc_type, IocType::Domain);
+        assert_eq!(ioc.severity, Severity::High);
+        assert_eq!(ioc.mitre_techniques, vec!["T1566.001".to_string()]);
+        assert_eq!(ioc.context.kill_chain_phases, vec!["initial-access".to_string()]);
+        assert_eq!(ioc.context.threat_actor.as_deref(), Some("APT28"));
+    }
+
+    #[test]
+    fn test_attribute_tlp_only_tightens() {
+        let tag = |name: &str| MispTag { name: name.to_string() };
+
+        let mut ctx = MispThreatContext::extract(&[tag("tlp:amber")], &[]);
+        ctx.merge(&MispThreatContext::extract(&[tag("tlp:white")], &[]));
+        assert_eq!(ctx.tlp, Some(Tlp::Amber));
+
+        ctx.merge(&MispThreatContext::extract(&[tag("tlp:red")], &[]));
+        assert_eq!(ctx.tlp, Some(Tlp::Red));
+        assert_eq!(Tlp::from_tags(&ctx.tags), Some(Tlp::Red));
+    }
+}
@@ -2,12 +2,17 @@
 //!
 //! Multi-source threat intelligence feed aggregation.
 
-use crate::{FeedConfig, FeedType, FeedStatus, FeedHealth, Indicator, IocType, Confidence, Reliability, IntelSource};
+use crate::{FeedConfig, FeedType, FeedStatus, Indicator, IocType, Confidence, Reliability, IntelSource};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::{info, warn, error};
 
+pub mod misp;
+pub mod scheduler;
+
+pub use scheduler::{BackoffPolicy, CacheValidators};
+
 /// Feed aggregator for multiple intelligence sources
 pub struct FeedAggregator {
     /// Configured feeds
@@ -18,6 +23,12 @@ pub struct FeedAggregator {
     indicator_tx: Option<mpsc::Sender<Indicator>>,
     /// HTTP client
     client: reqwest::Client,
+    /// MISP clients keyed by feed ID (kept to track incremental pulls)
+    misp_clients: dashmap::DashMap<String, Arc<misp::MispClient>>,
+    /// ETag/Last-Modified from each feed's last successful fetch
+    validators: dashmap::DashMap<String, CacheValidators>,
+    /// Retry policy for failing feeds
+    backoff: BackoffPolicy,
 }
 
 impl FeedAggregator {
@@ -30,9 +41,18 @@ impl FeedAggregator {
                 .timeout(std::time::Duration::from_secs(60))
                 .build()
                 .unwrap(),
+            misp_clients: dashmap::DashMap::new(),
+            validators: dashmap::DashMap::new(),
+            backoff: BackoffPolicy::default(),
         }
     }
     
+    /// Replace the retry policy for failing feeds
+    pub fn with_backoff(mut self, backoff: BackoffPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    
     /// Add a new feed
     pub fn add_feed(&self, config: FeedConfig) {
         let status = FeedStatus {
@@ -48,6 +68,24 @@ impl FeedAggregator {
     pub fn remove_feed(&self, feed_id: &str) {
         self.feeds.remove(feed_id);
         self.status.remove(feed_id);
+        self.misp_clients.remove(feed_id);
+        self.validators.remove(feed_id);
+    }
+    
+    /// Enabled feeds whose next poll time has passed
+    pub fn due_feeds(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<String> {
+        self.feeds.iter()
+            .filter(|feed| {
+                let next_poll = self.status.get(feed.key()).and_then(|s| s.next_poll);
+                scheduler::is_due(feed.value(), next_poll, now)
+            })
+            .map(|feed| feed.key().clone())
+            .collect()
+    }
+    
+    /// Number of enabled feeds
+    pub fn enabled_count(&self) -> usize {
+        self.feeds.iter().filter(|f| f.enabled).count()
     }
     
     /// Get feed status
@@ -80,71 +118,117 @@ impl FeedAggregator {
             FeedType::Custom => self.poll_custom(&config).await,
         };
         
-        // Update status
+        // Update status; failing feeds are retried on the backoff schedule
         if let Some(mut status) = self.status.get_mut(feed_id) {
-            status.last_poll = Some(chrono::Utc::now());
-            status.next_poll = Some(chrono::Utc::now() + chrono::Duration::from_std(config.poll_interval).unwrap_or_default());
-            
             match &result {
                 Ok(indicators) => {
-                    status.health = FeedHealth::Healthy;
+                    status.consecutive_failures = 0;
                     status.indicators_total += indicators.len() as u64;
                     status.indicators_active += indicators.len() as u64;
                     status.last_error = None;
                 }
                 Err(e) => {
-                    status.health = FeedHealth::Error;
+                    status.consecutive_failures += 1;
                     status.last_error = Some(format!("{}", e));
+                    warn!("Feed {} failed ({} in a row): {}", config.name, status.consecutive_failures, e);
                 }
             }
+            
+            let now = chrono::Utc::now();
+            let delay = self.backoff.next_delay(status.consecutive_failures, config.poll_interval);
+            status.health = self.backoff.health(status.consecutive_failures);
+            status.last_poll = Some(now);
+            status.next_poll = Some(now + chrono::Duration::from_std(delay).unwrap_or_default());
         }
         
         result
     }
     
-    /// Poll STIX/TAXII feed
-    async fn poll_stix_taxii(&self, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
-        info!("Polling STIX/TAXII feed: {}", config.name);
-        
+    /// Send a GET carrying the feed's cache validators; `None` means the
+    /// feed is unchanged since the last successful poll
+    async fn conditional_get(&self, config: &FeedConfig) -> Result<Option<(reqwest::Response, CacheValidators)>, FeedError> {
         let mut request = self.client.get(&config.url);
         
         if let Some(api_key) = &config.api_key {
             request = request.header("Authorization", format!("Bearer {}", api_key));
         }
+        if let Some(validators) = self.validators.get(&config.id) {
+            request = validators.apply(request);
+        }
         
         let response = request.send().await
             .map_err(|e| FeedError::Network(e.to_string()))?;
         
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            info!("Feed {} not modified", config.name);
+            return Ok(None);
+        }
         if !response.status().is_success() {
             return Err(FeedError::HttpError(response.status().as_u16()));
         }
         
+        let validators = CacheValidators::from_headers(response.headers());
+        Ok(Some((response, validators)))
+    }
+    
+    /// Remember validators once a fetched body has been parsed, so a body
+    /// that failed to parse is fetched in full again
+    fn store_validators(&self, config: &FeedConfig, validators: CacheValidators) {
+        if validators.is_empty() {
+            self.validators.remove(&config.id);
+        } else {
+            self.validators.insert(config.id.clone(), validators);
+        }
+    }
+    
+    /// Poll STIX/TAXII feed
+    async fn poll_stix_taxii(&self, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
+        info!("Polling STIX/TAXII feed: {}", config.name);
+        
+        let Some((response, validators)) = self.conditional_get(config).await? else {
+            return Ok(Vec::new());
+        };
+        
         let body = response.text().await
             .map_err(|e| FeedError::Network(e.to_string()))?;
         
         // Parse STIX bundle
-        crate::stix::parse_stix_bundle(&body, config)
+        let indicators = crate::stix::parse_stix_bundle(&body, config)?;
+        self.store_validators(config, validators);
+        Ok(indicators)
     }
     
-    /// Poll MISP feed
+    /// Poll MISP feed (REST API or manifest-based feed)
     async fn poll_misp(&self, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
         info!("Polling MISP feed: {}", config.name);
         
-        let mut request = self.client.get(&config.url);
-        
-        if let Some(api_key) = &config.api_key {
-            request = request.header("Authorization", api_key.clone());
+        self.misp_client(config).pull_indicators(config).await
+    }
+    
+    fn misp_client(&self, config: &FeedConfig) -> Arc<misp::MispClient> {
+        self.misp_clients
+            .entry(config.id.clone())
+            .or_insert_with(|| Arc::new(misp::MispClient::from_feed(config)))
+            .clone()
+    }
+    
+    /// Push a sighting back to every MISP feed the indicator came from
+    pub async fn report_sighting(&self, indicator: &Indicator) {
+        for source in &indicator.sources {
+            let Some(config) = self.feeds.get(&source.feed_id).map(|c| c.clone()) else {
+                continue;
+            };
+            if config.feed_type != FeedType::Misp {
+                continue;
+            }
+            
+            let client = self.misp_client(&config);
+            let sighting = client.sighting_for(indicator, misp::SightingType::Sighting);
+            
+            if let Err(e) = client.add_sighting(&sighting).await {
+                warn!("Failed to push MISP sighting for {} to {}: {}", indicator.value, config.name, e);
+            }
         }
-        
-        request = request.header("Accept", "application/json");
-        
-        let response = request.send().await
-            .map_err(|e| FeedError::Network(e.to_string()))?;
-        
-        let body: serde_json::Value = response.json().await
-            .map_err(|e| FeedError::Parse(e.to_string()))?;
-        
-        parse_misp_response(&body, config)
     }
     
     /// Poll OpenCTI feed
@@ -193,32 +277,32 @@ impl FeedAggregator {
     async fn poll_csv(&self, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
         info!("Polling CSV feed: {}", config.name);
         
-        let response = self.client.get(&config.url).send().await
-            .map_err(|e| FeedError::Network(e.to_string()))?;
+        let Some((response, validators)) = self.conditional_get(config).await? else {
+            return Ok(Vec::new());
+        };
         
         let body = response.text().await
             .map_err(|e| FeedError::Network(e.to_string()))?;
         
-        parse_csv_feed(&body, config)
+        let indicators = parse_csv_feed(&body, config)?;
+        self.store_validators(config, validators);
+        Ok(indicators)
     }
     
     /// Poll JSON API feed
     async fn poll_json(&self, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
         info!("Polling JSON API feed: {}", config.name);
         
-        let mut request = self.client.get(&config.url);
-        
-        if let Some(api_key) = &config.api_key {
-            request = request.header("Authorization", format!("Bearer {}", api_key));
-        }
-        
-        let response = request.send().await
-            .map_err(|e| FeedError::Network(e.to_string()))?;
+        let Some((response, validators)) = self.conditional_get(config).await? else {
+            return Ok(Vec::new());
+        };
         
         let body: serde_json::Value = response.json().await
             .map_err(|e| FeedError::Parse(e.to_string()))?;
         
-        parse_json_feed(&body, config)
+        let indicators = parse_json_feed(&body, config)?;
+        self.store_validators(config, validators);
+        Ok(indicators)
     }
     
     /// Poll RSS feed
@@ -247,6 +331,7 @@ pub enum FeedError {
     HttpError(u16),
     Parse(String),
     Timeout,
+    InvalidConfig(String),
 }
 
 impl std::fmt::Display for FeedError {
@@ -258,68 +343,11 @@ impl std::fmt::Display for FeedError {
             Self::HttpError(code) => write!(f, "HTTP error: {}", code),
             Self::Parse(e) => write!(f, "Parse error: {}", e),
             Self::Timeout => write!(f, "Timeout"),
+            Self::InvalidConfig(e) => write!(f, "Invalid feed configuration: {}", e),
         }
     }
 }
 
-/// Parse MISP response
-fn parse_misp_response(json: &serde_json::Value, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
-    let mut indicators = Vec::new();
-    
-    if let Some(events) = json.get("response").and_then(|r| r.as_array()) {
-        for event in events {
-            if let Some(attrs) = event.get("Attribute").and_then(|a| a.as_array()) {
-                for attr in attrs {
-                    if let Some(indicator) = parse_misp_attribute(attr, config) {
-                        indicators.push(indicator);
-                    }
-                }
-            }
-        }
-    }
-    
-    Ok(indicators)
-}
-
-fn parse_misp_attribute(attr: &serde_json::Value, config: &FeedConfig) -> Option<Indicator> {
-    let value = attr.get("value")?.as_str()?;
-    let attr_type = attr.get("type")?.as_str()?;
-    
-    let ioc_type = match attr_type {
-        "ip-src" | "ip-dst" => IocType::IPv4,
-        "domain" | "hostname" => IocType::Domain,
-        "url" => IocType::Url,
-        "md5" => IocType::FileHashMd5,
-        "sha1" => IocType::FileHashSha1,
-        "sha256" => IocType::FileHashSha256,
-        "email-src" | "email-dst" => IocType::Email,
-        _ => return None,
-    };
-    
-    Some(Indicator {
-        id: attr.get("uuid").and_then(|u| u.as_str()).unwrap_or("unknown").to_string(),
-        ioc_type,
-        value: value.to_string(),
-        confidence: config.default_confidence,
-        severity: crate::Severity::Medium,
-        first_seen: chrono::Utc::now(),
-        last_seen: chrono::Utc::now(),
-        expires_at: None,
-        sources: vec![IntelSource {
-            name: config.name.clone(),
-            feed_id: config.id.clone(),
-            reliability: config.reliability,
-            timestamp: chrono::Utc::now(),
-            reference_url: None,
-        }],
-        tags: config.tags.clone(),
-        context: crate::IocContext::default(),
-        mitre_tactics: Vec::new(),
-        mitre_techniques: Vec::new(),
-        related_iocs: Vec::new(),
-    })
-}
-
 /// Parse OpenCTI response
 fn parse_opencti_response(json: &serde_json::Value, config: &FeedConfig) -> Result<Vec<Indicator>, FeedError> {
     let mut indicators = Vec::new();
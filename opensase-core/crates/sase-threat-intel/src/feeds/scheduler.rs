@@ -0,0 +1,180 @@
+//! Feed Polling Schedule
+//!
+//! Per-feed due times and jittered exponential backoff. Feeds are polled on
+//! their own `poll_interval`; a failing feed is retried sooner than its
+//! interval at first and then backs off, so a dead upstream is not hammered
+//! and a transient blip does not cost a whole interval of intel.
+
+use crate::{FeedConfig, FeedHealth};
+use rand::Rng;
+use std::time::Duration;
+
+/// Retry policy for failing feeds
+#[derive(Debug, Clone)]
+pub struct BackoffPolicy {
+    /// Delay after the first failure
+    pub initial: Duration,
+    /// Upper bound on any delay (also capped by the feed's poll interval)
+    pub max: Duration,
+    /// Growth factor per consecutive failure
+    pub multiplier: f64,
+    /// Fraction of the delay randomised either way (0.2 = ±20%)
+    pub jitter: f64,
+    /// Consecutive failures before a feed is reported as `Error`
+    /// rather than `Degraded`
+    pub error_after: u32,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(30),
+            max: Duration::from_secs(3600),
+            multiplier: 2.0,
+            jitter: 0.2,
+            error_after: 3,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Un-jittered delay after `failures` consecutive failures
+    pub fn base_delay(&self, failures: u32, poll_interval: Duration) -> Duration {
+        if failures == 0 {
+            return poll_interval;
+        }
+
+        let cap = self.max.min(poll_interval).max(self.initial);
+        let exponent = failures.saturating_sub(1).min(32) as i32;
+        let secs = self.initial.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        Duration::from_secs_f64(secs.min(cap.as_secs_f64()))
+    }
+
+    /// Delay before the next poll, with jitter applied to retries
+    pub fn next_delay(&self, failures: u32, poll_interval: Duration) -> Duration {
+        let base = self.base_delay(failures, poll_interval);
+        if failures == 0 || self.jitter <= 0.0 {
+            return base;
+        }
+
+        let jitter = self.jitter.min(1.0);
+        let factor = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+        base.mul_f64(factor)
+    }
+
+    /// Health to report after `failures` consecutive failures
+    pub fn health(&self, failures: u32) -> FeedHealth {
+        match failures {
+            0 => FeedHealth::Healthy,
+            n if n < self.error_after => FeedHealth::Degraded,
+            _ => FeedHealth::Error,
+        }
+    }
+}
+
+/// HTTP cache validators from the last successful fetch of a feed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let header = |name: reqwest::header::HeaderName| headers.get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        Self {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Add conditional headers to a request
+    pub fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// Whether a feed should be polled now
+pub fn is_due(config: &FeedConfig, next_poll: Option<chrono::DateTime<chrono::Utc>>, now: chrono::DateTime<chrono::Utc>) -> bool {
+    config.enabled && next_poll.map(|next| next <= now).unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = BackoffPolicy { jitter: 0.0, ..Default::default() };
+        let interval = Duration::from_secs(900);
+
+        assert_eq!(policy.next_delay(0, interval), interval);
+        assert_eq!(policy.next_delay(1, interval), Duration::from_secs(30));
+        assert_eq!(policy.next_delay(2, interval), Duration::from_secs(60));
+        assert_eq!(policy.next_delay(3, interval), Duration::from_secs(120));
+        // Never wait longer than a healthy feed would
+        assert_eq!(policy.next_delay(10, interval), interval);
+    }
+
+    #[test]
+    fn test_backoff_jitter_bounds() {
+        let policy = BackoffPolicy::default();
+        let interval = Duration::from_secs(3600);
+
+        for _ in 0..100 {
+            let delay = policy.next_delay(2, interval).as_secs_f64();
+            assert!((47.9..=72.1).contains(&delay));
+        }
+    }
+
+    #[test]
+    fn test_health_degrades_then_errors() {
+        let policy = BackoffPolicy::default();
+        assert_eq!(policy.health(0), FeedHealth::Healthy);
+        assert_eq!(policy.health(1), FeedHealth::Degraded);
+        assert_eq!(policy.health(2), FeedHealth::Degraded);
+        assert_eq!(policy.health(3), FeedHealth::Error);
+    }
+
+    #[tokio::test]
+    async fn test_failed_poll_schedules_retry() {
+        let aggregator = super::super::FeedAggregator::new()
+            .with_backoff(BackoffPolicy { jitter: 0.0, ..Default::default() });
+        aggregator.add_feed(FeedConfig {
+            id: "broken".to_string(),
+            name: "Broken".to_string(),
+            feed_type: crate::FeedType::CsvFile,
+            url: "not a url".to_string(),
+            api_key: None,
+            poll_interval: Duration::from_secs(3600),
+            enabled: true,
+            reliability: crate::Reliability::C,
+            default_confidence: crate::Confidence::Medium,
+            ioc_types: vec![],
+            tags: vec![],
+        });
+
+        let now = chrono::Utc::now();
+        assert_eq!(aggregator.due_feeds(now), vec!["broken".to_string()]);
+        assert!(aggregator.poll_feed("broken").await.is_err());
+
+        let status = aggregator.get_status("broken").unwrap();
+        assert_eq!(status.health, FeedHealth::Degraded);
+        assert_eq!(status.consecutive_failures, 1);
+        assert_eq!(status.next_poll.unwrap() - status.last_poll.unwrap(), chrono::Duration::seconds(30));
+        assert!(aggregator.due_feeds(now).is_empty());
+    }
+}
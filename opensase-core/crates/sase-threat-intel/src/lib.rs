@@ -8,6 +8,7 @@
 //! - Real-time IoC correlation and enrichment
 //! - MITRE ATT&CK framework mapping
 //! - Automatic distribution to SASE components
+//! - STIX 2.1 export and TAXII 2.1 collections for partner sharing
 //!
 //! # Architecture
 //! ```text
@@ -57,6 +58,11 @@ pub mod sinkhole;
 pub mod matching;
 pub mod hunting;
 pub mod api;
+pub mod store;
+pub mod export;
+pub mod taxii;
+pub mod snapshot;
+pub mod soar;
 
 // =============================================================================
 // Indicator of Compromise (IoC) Types
@@ -243,6 +249,9 @@ pub struct FeedStatus {
     pub indicators_active: u64,
     pub indicators_expired: u64,
     pub health: FeedHealth,
+    /// Failed polls since the last success
+    #[serde(default)]
+    pub consecutive_failures: u32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -263,9 +272,20 @@ pub struct ThreatIntelService {
     config: ThreatIntelConfig,
     feeds: feeds::FeedAggregator,
     correlator: correlator::Correlator,
-    enricher: enrichment::Enricher,
+    enricher: std::sync::Arc<enrichment::Enricher>,
+    /// Newly ingested indicators awaiting enrichment
+    enrich_tx: tokio::sync::mpsc::Sender<Indicator>,
+    enrich_rx: parking_lot::Mutex<Option<tokio::sync::mpsc::Receiver<Indicator>>>,
     distributor: distribution::Distributor,
     indicators: dashmap::DashMap<IocId, Indicator>,
+    /// Cold tier; absent when running memory-only
+    store: Option<Box<dyn store::IndicatorStore>>,
+    /// CIDR indicator keys for covering-network lookups
+    cidr_index: matching::CidrTrie<String>,
+    /// Announced prefix -> origin ASN, for matching ASN indicators by IP
+    asn_index: matching::CidrTrie<u32>,
+    /// Wildcard domain indicator keys for subdomain lookups
+    wildcard_index: matching::DomainSuffixTree<String>,
     stats: ThreatIntelStats,
 }
 
@@ -279,8 +299,14 @@ pub struct ThreatIntelConfig {
     pub min_distribute_confidence: Confidence,
     /// Enable enrichment
     pub enable_enrichment: bool,
+    /// Minimum severity queued for enrichment
+    pub enrich_min_severity: Severity,
+    /// Indicators waiting for enrichment before new ones are skipped
+    pub enrichment_queue_size: usize,
     /// Enable MITRE mapping
     pub enable_mitre_mapping: bool,
+    /// Minimum confidence warm-loaded from the cold tier at startup
+    pub warm_load_min_confidence: Confidence,
 }
 
 impl Default for ThreatIntelConfig {
@@ -290,7 +316,10 @@ impl Default for ThreatIntelConfig {
             default_ttl_days: 30,
             min_distribute_confidence: Confidence::Medium,
             enable_enrichment: true,
+            enrich_min_severity: Severity::High,
+            enrichment_queue_size: 10_000,
             enable_mitre_mapping: true,
+            warm_load_min_confidence: Confidence::High,
         }
     }
 }
@@ -303,54 +332,256 @@ pub struct ThreatIntelStats {
     pub lookups_total: std::sync::atomic::AtomicU64,
     pub lookups_hits: std::sync::atomic::AtomicU64,
     pub distributions_total: std::sync::atomic::AtomicU64,
+    pub removals_total: std::sync::atomic::AtomicU64,
+    pub distribution_failures: std::sync::atomic::AtomicU64,
+    /// Delivery status per sink, refreshed on each flush
+    pub sinks: dashmap::DashMap<String, distribution::SinkDeliveryStatus>,
 }
 
 impl ThreatIntelService {
     pub fn new(config: ThreatIntelConfig) -> Self {
+        let (enrich_tx, enrich_rx) = tokio::sync::mpsc::channel(config.enrichment_queue_size.max(1));
+        
         Self {
             config,
             feeds: feeds::FeedAggregator::new(),
             correlator: correlator::Correlator::new(),
-            enricher: enrichment::Enricher::new(),
+            enricher: std::sync::Arc::new(enrichment::Enricher::new()),
+            enrich_tx,
+            enrich_rx: parking_lot::Mutex::new(Some(enrich_rx)),
             distributor: distribution::Distributor::new(),
             indicators: dashmap::DashMap::new(),
+            store: None,
+            cidr_index: matching::CidrTrie::new(),
+            asn_index: matching::CidrTrie::new(),
+            wildcard_index: matching::DomainSuffixTree::new(),
             stats: ThreatIntelStats::default(),
         }
     }
     
+    /// Create a service backed by a persistent cold tier, warm-loading
+    /// high-confidence indicators into memory
+    pub fn with_store(config: ThreatIntelConfig, store: impl store::IndicatorStore + 'static) -> Self {
+        use std::sync::atomic::Ordering;
+        
+        let mut service = Self::new(config);
+        
+        let warm = store.warm_set(service.config.warm_load_min_confidence, service.config.max_indicators);
+        for (key, indicator) in warm {
+            service.stats.indicators_by_type
+                .entry(indicator.ioc_type)
+                .and_modify(|c| *c += 1)
+                .or_insert(1);
+            service.indicators.insert(key, indicator);
+        }
+        
+        // Covering indexes span both tiers
+        for (key, _) in store.iter() {
+            service.index(&key);
+        }
+        service.stats.indicators_total.store(store.len() as u64, Ordering::Relaxed);
+        
+        tracing::info!(
+            "Warm-loaded {} of {} stored indicators",
+            service.indicators.len(),
+            store.len()
+        );
+        
+        service.store = Some(Box::new(store));
+        service
+    }
+    
+    /// Replace the enrichment engine
+    pub fn with_enricher(mut self, enricher: enrichment::Enricher) -> Self {
+        self.enricher = std::sync::Arc::new(enricher);
+        self
+    }
+    
+    /// Let the correlator sinkhole and unsinkhole domains
+    pub fn with_sinkhole(mut self, sinkhole: std::sync::Arc<sinkhole::DnsSinkhole>) -> Self {
+        self.correlator = std::mem::take(&mut self.correlator).with_sinkhole(sinkhole);
+        self
+    }
+    
+    pub fn correlator(&self) -> &correlator::Correlator {
+        &self.correlator
+    }
+    
+    /// Replace the distribution pipeline
+    pub fn with_distributor(mut self, distributor: distribution::Distributor) -> Self {
+        self.distributor = distributor;
+        self
+    }
+    
+    /// Replace the retry policy for failing feeds
+    pub fn with_feed_backoff(mut self, backoff: feeds::BackoffPolicy) -> Self {
+        self.feeds = std::mem::take(&mut self.feeds).with_backoff(backoff);
+        self
+    }
+    
     /// Add a new feed
     pub fn add_feed(&self, config: FeedConfig) {
         self.feeds.add_feed(config);
     }
     
+    /// Poll one feed and ingest what it returns
+    pub async fn poll_and_ingest(&self, feed_id: &str) -> Result<usize, feeds::FeedError> {
+        let indicators = self.feeds.poll_feed(feed_id).await?;
+        let fetched = indicators.len();
+        for indicator in indicators {
+            self.ingest(indicator);
+        }
+        Ok(fetched)
+    }
+    
+    /// Poll each feed on its own interval, checking for due feeds every
+    /// `tick`; failing feeds follow the aggregator's backoff policy
+    pub fn spawn_feed_polling(self: &std::sync::Arc<Self>, tick: Duration) -> tokio::task::JoinHandle<()> {
+        use std::sync::atomic::Ordering;
+        
+        let service = std::sync::Arc::clone(self);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(tick);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut in_flight = tokio::task::JoinSet::new();
+            let mut polling: HashSet<String> = HashSet::new();
+            
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        service.stats.feeds_active
+                            .store(service.feeds.enabled_count() as u32, Ordering::Relaxed);
+                        
+                        for feed_id in service.feeds.due_feeds(chrono::Utc::now()) {
+                            if !polling.insert(feed_id.clone()) {
+                                continue;
+                            }
+                            let service = std::sync::Arc::clone(&service);
+                            in_flight.spawn(async move {
+                                let result = service.poll_and_ingest(&feed_id).await;
+                                (feed_id, result)
+                            });
+                        }
+                    }
+                    Some(joined) = in_flight.join_next(), if !in_flight.is_empty() => {
+                        if let Ok((feed_id, result)) = joined {
+                            if let Ok(fetched) = result {
+                                tracing::debug!("Feed {} returned {} indicators", feed_id, fetched);
+                            }
+                            polling.remove(&feed_id);
+                        }
+                    }
+                }
+            }
+        })
+    }
+    
     /// Lookup an indicator
     pub fn lookup(&self, ioc_type: IocType, value: &str) -> Option<Indicator> {
         use std::sync::atomic::Ordering;
         
         self.stats.lookups_total.fetch_add(1, Ordering::Relaxed);
         
-        let key = format!("{}:{}", ioc_type_to_string(ioc_type), value.to_lowercase());
-        
-        if let Some(indicator) = self.indicators.get(&key) {
-            self.stats.lookups_hits.fetch_add(1, Ordering::Relaxed);
+        let indicator = self.fetch(&indicator_key(ioc_type, value))?;
+        self.stats.lookups_hits.fetch_add(1, Ordering::Relaxed);
+        Some(indicator)
+    }
+    
+    /// Fetch by key from the hot tier, falling back to the cold tier and
+    /// promoting on hit
+    fn fetch(&self, key: &str) -> Option<Indicator> {
+        if let Some(indicator) = self.indicators.get(key) {
             return Some(indicator.clone());
         }
         
-        None
+        let indicator = self.cold_get(key)?;
+        if indicator.expires_at.map(|e| e <= chrono::Utc::now()).unwrap_or(false) {
+            return None;
+        }
+        
+        if self.indicators.len() < self.config.max_indicators {
+            self.indicators.insert(key.to_string(), indicator.clone());
+        }
+        
+        Some(indicator)
     }
     
-    /// Lookup IP address
+    /// Resolve the first live indicator among covering keys
+    fn fetch_covering(&self, keys: Vec<String>) -> Option<Indicator> {
+        use std::sync::atomic::Ordering;
+        
+        let indicator = keys.iter().find_map(|key| self.fetch(key))?;
+        self.stats.lookups_hits.fetch_add(1, Ordering::Relaxed);
+        Some(indicator)
+    }
+    
+    /// Lookup IP address, falling back to the most specific covering CIDR
+    /// and then to an indicator on the origin ASN
     pub fn lookup_ip(&self, ip: IpAddr) -> Option<Indicator> {
         let ioc_type = match ip {
             IpAddr::V4(_) => IocType::IPv4,
             IpAddr::V6(_) => IocType::IPv6,
         };
         self.lookup(ioc_type, &ip.to_string())
+            .or_else(|| self.fetch_covering(self.cidr_index.covering(ip)))
+            .or_else(|| self.origin_asn(ip).and_then(|asn| self.fetch_asn(asn)))
+    }
+    
+    /// Every indicator matching an IP: the exact address, each covering
+    /// CIDR (most specific first) and the origin ASN
+    pub fn lookup_ip_all(&self, ip: IpAddr) -> Vec<Indicator> {
+        use std::sync::atomic::Ordering;
+        
+        self.stats.lookups_total.fetch_add(1, Ordering::Relaxed);
+        let exact = match ip {
+            IpAddr::V4(_) => indicator_key(IocType::IPv4, &ip.to_string()),
+            IpAddr::V6(_) => indicator_key(IocType::IPv6, &ip.to_string()),
+        };
+        
+        let mut matches: Vec<Indicator> = std::iter::once(exact)
+            .chain(self.cidr_index.covering(ip))
+            .filter_map(|key| self.fetch(&key))
+            .collect();
+        matches.extend(self.origin_asn(ip).and_then(|asn| self.fetch_asn(asn)));
+        
+        if !matches.is_empty() {
+            self.stats.lookups_hits.fetch_add(1, Ordering::Relaxed);
+        }
+        matches
+    }
+    
+    /// Lookup ASN indicator by AS number
+    pub fn lookup_asn(&self, asn: u32) -> Option<Indicator> {
+        use std::sync::atomic::Ordering;
+        
+        self.stats.lookups_total.fetch_add(1, Ordering::Relaxed);
+        let indicator = self.fetch_asn(asn)?;
+        self.stats.lookups_hits.fetch_add(1, Ordering::Relaxed);
+        Some(indicator)
+    }
+    
+    /// ASN indicators are keyed as ingested, with or without the `AS` prefix
+    fn fetch_asn(&self, asn: u32) -> Option<Indicator> {
+        self.fetch(&indicator_key(IocType::Asn, &asn.to_string()))
+            .or_else(|| self.fetch(&indicator_key(IocType::Asn, &format!("AS{}", asn))))
+    }
+    
+    /// Register an announced prefix and its origin ASN (from a BGP table
+    /// or routing registry dump)
+    pub fn add_asn_prefix(&self, cidr: &str, asn: u32) -> bool {
+        self.asn_index.insert_str(cidr, asn)
+    }
+    
+    /// Origin ASN of the most specific announced prefix containing `ip`
+    pub fn origin_asn(&self, ip: IpAddr) -> Option<u32> {
+        self.asn_index.longest_match(ip)
     }
     
-    /// Lookup domain
+    /// Lookup domain, falling back to the most specific covering wildcard
     pub fn lookup_domain(&self, domain: &str) -> Option<Indicator> {
         self.lookup(IocType::Domain, domain)
+            .or_else(|| self.fetch_covering(self.wildcard_index.covering(domain)))
     }
     
     /// Lookup URL
@@ -374,10 +605,13 @@ impl ThreatIntelService {
     pub fn ingest(&self, indicator: Indicator) {
         use std::sync::atomic::Ordering;
         
-        let key = format!("{}:{}", ioc_type_to_string(indicator.ioc_type), indicator.value.to_lowercase());
+        let key = indicator_key(indicator.ioc_type, &indicator.value);
         
-        // Merge if exists
-        if let Some(mut existing) = self.indicators.get_mut(&key) {
+        // Merge if exists (in either tier)
+        let existing = self.indicators.get(&key).map(|i| i.clone())
+            .or_else(|| self.cold_get(&key));
+        
+        if let Some(mut existing) = existing {
             // Update last seen
             existing.last_seen = indicator.last_seen;
             
@@ -389,17 +623,32 @@ impl ThreatIntelService {
             }
             
             // Update confidence based on number of sources
+            let previous_confidence = existing.confidence;
             if existing.sources.len() >= 3 {
                 existing.confidence = Confidence::High;
             } else if existing.sources.len() >= 2 {
                 existing.confidence = std::cmp::max(existing.confidence, Confidence::Medium);
             }
             
+            self.cold_put(&key, &existing);
+            if existing.confidence > previous_confidence {
+                self.distribute(&existing);
+            }
+            if let Some(mut hot) = self.indicators.get_mut(&key) {
+                *hot = existing;
+            }
+            
             return;
         }
         
-        // Insert new
-        self.indicators.insert(key, indicator.clone());
+        // Insert new; once the hot tier is full new indicators live in the cold tier only
+        self.index(&key);
+        self.cold_put(&key, &indicator);
+        self.distribute(&indicator);
+        self.queue_enrichment(&indicator);
+        if self.store.is_none() || self.indicators.len() < self.config.max_indicators {
+            self.indicators.insert(key, indicator.clone());
+        }
         self.stats.indicators_total.fetch_add(1, Ordering::Relaxed);
         
         // Update type counter
@@ -409,6 +658,184 @@ impl ThreatIntelService {
             .or_insert(1);
     }
     
+    fn queue_enrichment(&self, indicator: &Indicator) {
+        if !self.config.enable_enrichment
+            || indicator.severity < self.config.enrich_min_severity
+            || !self.enricher.has_providers()
+        {
+            return;
+        }
+        
+        if self.enrich_tx.try_send(indicator.clone()).is_err() {
+            tracing::debug!("Enrichment queue full, skipping {}", indicator.value);
+        }
+    }
+    
+    /// Run the enrichment pipeline; returns `None` if already running
+    pub fn spawn_enrichment(self: &std::sync::Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let mut rx = self.enrich_rx.lock().take()?;
+        let service = std::sync::Arc::clone(self);
+        
+        Some(tokio::spawn(async move {
+            let mut in_flight = tokio::task::JoinSet::new();
+            
+            while let Some(mut indicator) = rx.recv().await {
+                while in_flight.len() >= service.enricher.max_concurrent() {
+                    in_flight.join_next().await;
+                }
+                
+                let service = std::sync::Arc::clone(&service);
+                in_flight.spawn(async move {
+                    service.enricher.enrich(&mut indicator).await;
+                    service.apply_enrichment(&indicator);
+                });
+            }
+        }))
+    }
+    
+    /// Copy enriched context onto the stored indicator
+    fn apply_enrichment(&self, enriched: &Indicator) {
+        let key = indicator_key(enriched.ioc_type, &enriched.value);
+        let Some(mut current) = self.indicators.get(&key).map(|i| i.clone())
+            .or_else(|| self.cold_get(&key))
+        else {
+            return;
+        };
+        
+        if enriched.context.geo_location.is_some() {
+            current.context.geo_location = enriched.context.geo_location.clone();
+        }
+        if enriched.context.whois.is_some() {
+            current.context.whois = enriched.context.whois.clone();
+        }
+        if !enriched.context.dns_records.is_empty() {
+            current.context.dns_records = enriched.context.dns_records.clone();
+        }
+        let escalated = enriched.severity > current.severity;
+        current.severity = std::cmp::max(current.severity, enriched.severity);
+        
+        self.cold_put(&key, &current);
+        if escalated {
+            self.distribute(&current);
+        }
+        if let Some(mut hot) = self.indicators.get_mut(&key) {
+            *hot = current;
+        }
+    }
+    
+    /// Queue an indicator for enforcement points
+    fn distribute(&self, indicator: &Indicator) {
+        if indicator.confidence >= self.config.min_distribute_confidence {
+            self.distributor.enqueue(indicator);
+        }
+    }
+    
+    pub fn distributor(&self) -> &distribution::Distributor {
+        &self.distributor
+    }
+    
+    /// Live indicators eligible for distribution
+    pub fn distributable(&self, ioc_type: IocType) -> Vec<Indicator> {
+        let min_confidence = self.config.min_distribute_confidence;
+        self.select(|i| i.ioc_type == ioc_type && i.confidence >= min_confidence)
+    }
+    
+    /// Push queued additions and removals to all sinks
+    pub async fn flush_distribution(&self) -> Vec<distribution::SinkFlushReport> {
+        use std::sync::atomic::Ordering;
+        
+        let reports = self.distributor.flush().await;
+        
+        for report in &reports {
+            self.stats.distributions_total.fetch_add(report.delivered, Ordering::Relaxed);
+            self.stats.removals_total.fetch_add(report.removed, Ordering::Relaxed);
+            if report.error.is_some() {
+                self.stats.distribution_failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        for status in self.distributor.sink_status() {
+            self.stats.sinks.insert(status.sink.clone(), status);
+        }
+        
+        reports
+    }
+    
+    /// Periodically flush the distribution pipeline
+    pub fn spawn_distribution(self: &std::sync::Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let service = std::sync::Arc::clone(self);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                service.flush_distribution().await;
+            }
+        })
+    }
+    
+    /// Add a key to the covering indexes if it is a CIDR or wildcard domain
+    fn index(&self, key: &str) {
+        if let Some(cidr) = key.strip_prefix("cidr:") {
+            self.cidr_index.insert_str(cidr, key.to_string());
+        } else if let Some(domain) = key.strip_prefix("domain:") {
+            if domain.starts_with("*.") {
+                self.wildcard_index.insert(domain, key.to_string());
+            }
+        }
+    }
+    
+    fn unindex(&self, key: &str) {
+        if let Some(cidr) = key.strip_prefix("cidr:") {
+            if let Some((network, prefix_len)) = matching::parse_cidr(cidr) {
+                self.cidr_index.remove(network, prefix_len);
+            }
+        } else if let Some(domain) = key.strip_prefix("domain:") {
+            if domain.starts_with("*.") {
+                self.wildcard_index.remove(domain);
+            }
+        }
+    }
+    
+    fn cold_get(&self, key: &str) -> Option<Indicator> {
+        let store = self.store.as_ref()?;
+        match store.get(key) {
+            Ok(indicator) => indicator,
+            Err(e) => {
+                tracing::warn!("Cold tier read failed for {}: {}", key, e);
+                None
+            }
+        }
+    }
+    
+    fn cold_put(&self, key: &str, indicator: &Indicator) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.put(key, indicator) {
+                tracing::warn!("Cold tier write failed for {}: {}", key, e);
+            }
+        }
+    }
+    
+    /// Live indicators matching `filter` across both tiers
+    pub fn select(&self, filter: impl Fn(&Indicator) -> bool) -> Vec<Indicator> {
+        let now = chrono::Utc::now();
+        let live = |i: &Indicator| i.expires_at.map(|e| e > now).unwrap_or(true);
+        
+        let mut selected: Vec<Indicator> = self.indicators.iter()
+            .filter(|e| live(e.value()) && filter(e.value()))
+            .map(|e| e.value().clone())
+            .collect();
+        
+        if let Some(store) = &self.store {
+            for (key, indicator) in store.iter() {
+                if !self.indicators.contains_key(&key) && live(&indicator) && filter(&indicator) {
+                    selected.push(indicator);
+                }
+            }
+        }
+        
+        selected
+    }
+    
     /// Get statistics snapshot
     pub fn get_stats(&self) -> ThreatIntelSnapshot {
         use std::sync::atomic::Ordering;
@@ -419,20 +846,64 @@ impl ThreatIntelService {
             lookups_total: self.stats.lookups_total.load(Ordering::Relaxed),
             lookups_hits: self.stats.lookups_hits.load(Ordering::Relaxed),
             distributions_total: self.stats.distributions_total.load(Ordering::Relaxed),
+            removals_total: self.stats.removals_total.load(Ordering::Relaxed),
+            distribution_failures: self.stats.distribution_failures.load(Ordering::Relaxed),
+            sinks: self.stats.sinks.iter().map(|s| s.value().clone()).collect(),
         }
     }
     
-    /// Cleanup expired indicators
+    /// Cleanup expired indicators in both tiers
     pub fn cleanup_expired(&self) {
         let now = chrono::Utc::now();
         
-        self.indicators.retain(|_, indicator| {
-            if let Some(expires) = indicator.expires_at {
-                expires > now
-            } else {
-                true
+        self.indicators.retain(|key, indicator| {
+            let live = indicator.expires_at.map(|e| e > now).unwrap_or(true);
+            if !live {
+                self.distributor.retract(indicator);
+                if self.store.is_none() {
+                    self.unindex(key);
+                }
             }
+            live
         });
+        
+        if let Some(store) = &self.store {
+            match store.compact_expired(now) {
+                Ok(removed) => {
+                    for (key, indicator) in &removed {
+                        self.unindex(key);
+                        self.distributor.retract(indicator);
+                    }
+                    let total = &self.stats.indicators_total;
+                    let _ = total.fetch_update(
+                        std::sync::atomic::Ordering::Relaxed,
+                        std::sync::atomic::Ordering::Relaxed,
+                        |t| Some(t.saturating_sub(removed.len() as u64)),
+                    );
+                }
+                Err(e) => tracing::warn!("Cold tier compaction failed: {}", e),
+            }
+        }
+    }
+    
+    /// Periodically compact expired indicators and flush the cold tier
+    pub fn spawn_compaction(self: &std::sync::Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let service = std::sync::Arc::clone(self);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            
+            loop {
+                ticker.tick().await;
+                service.cleanup_expired();
+                if let Some(store) = &service.store {
+                    if let Err(e) = store.flush() {
+                        tracing::warn!("Cold tier flush failed: {}", e);
+                    }
+                }
+            }
+        })
     }
 }
 
@@ -443,6 +914,14 @@ pub struct ThreatIntelSnapshot {
     pub lookups_total: u64,
     pub lookups_hits: u64,
     pub distributions_total: u64,
+    pub removals_total: u64,
+    pub distribution_failures: u64,
+    pub sinks: Vec<distribution::SinkDeliveryStatus>,
+}
+
+/// Storage key for an indicator
+fn indicator_key(ioc_type: IocType, value: &str) -> String {
+    format!("{}:{}", ioc_type_to_string(ioc_type), value.to_lowercase())
 }
 
 fn ioc_type_to_string(ioc_type: IocType) -> &'static str {
@@ -501,4 +980,133 @@ mod tests {
         let result = service.lookup(IocType::IPv4, "192.168.1.1");
         assert!(result.is_some());
     }
+    
+    #[test]
+    fn test_cold_tier_spill_and_warm_load() {
+        let ioc = |value: &str, confidence: Confidence, expires_at| Indicator {
+            id: value.to_string(),
+            ioc_type: IocType::Domain,
+            value: value.to_string(),
+            confidence,
+            severity: Severity::High,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at,
+            sources: vec![],
+            tags: vec![],
+            context: IocContext::default(),
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            related_iocs: vec![],
+        };
+        
+        let config = ThreatIntelConfig { max_indicators: 1, ..Default::default() };
+        let service = ThreatIntelService::with_store(config, store::SledStore::temporary().unwrap());
+        
+        service.ingest(ioc("hot.example", Confidence::High, None));
+        service.ingest(ioc("cold.example", Confidence::Low, None));
+        service.ingest(ioc("stale.example", Confidence::Low, Some(chrono::Utc::now() - chrono::Duration::hours(1))));
+        assert_eq!(service.indicators.len(), 1);
+        
+        // Cold-only indicators are still found
+        assert!(service.lookup(IocType::Domain, "cold.example").is_some());
+        assert!(service.lookup(IocType::Domain, "stale.example").is_none());
+        
+        service.cleanup_expired();
+        let store = service.store.as_ref().unwrap();
+        assert_eq!(store.len(), 2);
+        
+        let warm = store.warm_set(Confidence::High, 10);
+        assert_eq!(warm.len(), 1);
+        assert_eq!(warm[0].1.value, "hot.example");
+    }
+    
+    #[test]
+    fn test_rehydrate_after_restart() {
+        let ioc = |ioc_type, value: &str| Indicator {
+            id: value.to_string(),
+            ioc_type,
+            value: value.to_string(),
+            confidence: Confidence::High,
+            severity: Severity::High,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at: None,
+            sources: vec![],
+            tags: vec![],
+            context: IocContext::default(),
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            related_iocs: vec![],
+        };
+        let path = std::env::temp_dir().join(format!("ioc-store-{}", uuid::Uuid::new_v4()));
+
+        {
+            let cold = store::WriteBehindStore::new(
+                store::SledStore::open_path(&path).unwrap(),
+                store::WriteBehindConfig::default(),
+            );
+            let service = ThreatIntelService::with_store(ThreatIntelConfig::default(), cold);
+            service.ingest(ioc(IocType::Domain, "c2.example"));
+            service.ingest(ioc(IocType::Cidr, "203.0.113.0/24"));
+        }
+
+        // Buffered writes are flushed on drop and the indexes are rebuilt
+        let service = ThreatIntelService::with_store(
+            ThreatIntelConfig::default(),
+            store::SledStore::open_path(&path).unwrap(),
+        );
+        assert_eq!(service.indicators.len(), 2);
+        assert!(service.lookup_domain("c2.example").is_some());
+        assert!(service.lookup_ip("203.0.113.7".parse().unwrap()).is_some());
+
+        drop(service);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_covering_lookups() {
+        let service = ThreatIntelService::new(ThreatIntelConfig::default());
+        let ioc = |ioc_type, value: &str| Indicator {
+            id: value.to_string(),
+            ioc_type,
+            value: value.to_string(),
+            confidence: Confidence::Medium,
+            severity: Severity::High,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at: None,
+            sources: vec![],
+            tags: vec![],
+            context: IocContext::default(),
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            related_iocs: vec![],
+        };
+        
+        service.ingest(ioc(IocType::Cidr, "203.0.113.0/24"));
+        service.ingest(ioc(IocType::Domain, "*.evil.com"));
+        
+        let hit = service.lookup_ip("203.0.113.9".parse().unwrap()).unwrap();
+        assert_eq!(hit.value, "203.0.113.0/24");
+        assert!(service.lookup_ip("198.51.100.1".parse().unwrap()).is_none());
+        
+        let hit = service.lookup_domain("sub.evil.com").unwrap();
+        assert_eq!(hit.value, "*.evil.com");
+        assert!(service.lookup_domain("evil.com").is_none());
+        
+        service.ingest(ioc(IocType::Cidr, "203.0.0.0/16"));
+        service.ingest(ioc(IocType::Asn, "AS64496"));
+        assert!(service.add_asn_prefix("203.0.0.0/12", 64496));
+        
+        let all: Vec<String> = service.lookup_ip_all("203.0.113.9".parse().unwrap())
+            .into_iter()
+            .map(|i| i.value)
+            .collect();
+        assert_eq!(all, vec!["203.0.113.0/24", "203.0.0.0/16", "AS64496"]);
+        
+        let hit = service.lookup_ip("203.1.0.1".parse().unwrap()).unwrap();
+        assert_eq!(hit.value, "AS64496");
+        assert_eq!(service.origin_asn("203.1.0.1".parse().unwrap()), Some(64496));
+    }
 }
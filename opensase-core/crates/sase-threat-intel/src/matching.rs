@@ -3,7 +3,7 @@
 //! Sub-microsecond IOC lookups using bloom filters and optimized data structures.
 
 use crate::{Indicator, IocType, Confidence, Severity};
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
 
@@ -24,6 +24,9 @@ pub struct IocMatchingEngine {
     /// Domain suffix tree for subdomain matching
     domain_suffixes: DomainSuffixTree,
     
+    /// Prefix trie for CIDR indicators
+    cidr_trie: CidrTrie,
+    
     /// Statistics
     stats: MatchingStats,
 }
@@ -109,82 +112,321 @@ fn hash_with_seed(data: &[u8], seed: u64) -> u64 {
 }
 
 /// Domain suffix tree for efficient subdomain matching
-pub struct DomainSuffixTree {
-    root: DomainNode,
+///
+/// Labels are stored right-to-left (`com` -> `evil` -> `sub`). A plain
+/// domain matches only itself; a `*.` wildcard matches every subdomain
+/// below it but not the apex.
+pub struct DomainSuffixTree<T = IocMatch> {
+    root: parking_lot::RwLock<DomainNode<T>>,
+}
+
+struct DomainNode<T> {
+    children: HashMap<String, DomainNode<T>>,
+    exact: Option<T>,
+    wildcard: Option<T>,
 }
 
-struct DomainNode {
-    children: dashmap::DashMap<String, DomainNode>,
-    is_terminal: bool,
-    match_info: Option<IocMatch>,
+impl<T> DomainNode<T> {
+    fn new() -> Self {
+        Self {
+            children: HashMap::new(),
+            exact: None,
+            wildcard: None,
+        }
+    }
 }
 
-impl DomainSuffixTree {
+impl<T: Clone> DomainSuffixTree<T> {
     pub fn new() -> Self {
         Self {
-            root: DomainNode {
-                children: dashmap::DashMap::new(),
-                is_terminal: false,
-                match_info: None,
-            },
+            root: parking_lot::RwLock::new(DomainNode::new()),
         }
     }
     
-    pub fn insert(&self, domain: &str, match_info: IocMatch) {
-        let parts: Vec<&str> = domain.split('.').rev().collect();
-        let mut current = &self.root;
+    /// Insert a domain or `*.` wildcard
+    pub fn insert(&self, domain: &str, value: T) {
+        let (name, wildcard) = split_wildcard(domain);
+        let mut root = self.root.write();
+        let mut current = &mut *root;
+        
+        for label in name.rsplit('.') {
+            current = current.children
+                .entry(label.to_string())
+                .or_insert_with(DomainNode::new);
+        }
         
-        for (i, part) in parts.iter().enumerate() {
-            let part_str = part.to_lowercase();
-            
-            if !current.children.contains_key(&part_str) {
-                current.children.insert(part_str.clone(), DomainNode {
-                    children: dashmap::DashMap::new(),
-                    is_terminal: false,
-                    match_info: None,
-                });
+        if wildcard {
+            current.wildcard = Some(value);
+        } else {
+            current.exact = Some(value);
+        }
+    }
+    
+    /// Remove a domain or `*.` wildcard
+    pub fn remove(&self, domain: &str) -> Option<T> {
+        let (name, wildcard) = split_wildcard(domain);
+        let mut root = self.root.write();
+        let mut current = &mut *root;
+        
+        for label in name.rsplit('.') {
+            current = current.children.get_mut(label)?;
+        }
+        
+        if wildcard {
+            current.wildcard.take()
+        } else {
+            current.exact.take()
+        }
+    }
+    
+    /// Most specific entry covering the domain
+    pub fn search(&self, domain: &str) -> Option<T> {
+        self.covering(domain).into_iter().next()
+    }
+    
+    /// All entries covering the domain, most specific first
+    pub fn covering(&self, domain: &str) -> Vec<T> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let labels: Vec<&str> = domain.rsplit('.').collect();
+        let root = self.root.read();
+        let mut current = &*root;
+        let mut matches = Vec::new();
+        
+        for (depth, label) in labels.iter().enumerate() {
+            // A wildcard at this level covers anything strictly below it
+            if let Some(v) = &current.wildcard {
+                if depth > 0 {
+                    matches.push(v.clone());
+                }
             }
             
-            if let Some(node) = current.children.get(&part_str) {
-                if i == parts.len() - 1 {
-                    // Update terminal node
-                    let mut node = current.children.get_mut(&part_str).unwrap();
-                    node.is_terminal = true;
-                    node.match_info = Some(match_info.clone());
+            match current.children.get(*label) {
+                Some(node) => current = node,
+                None => {
+                    matches.reverse();
+                    return matches;
                 }
-                // Move to next level (simplified for this implementation)
             }
         }
+        
+        matches.reverse();
+        if let Some(v) = &current.exact {
+            matches.insert(0, v.clone());
+        }
+        matches
     }
     
-    pub fn search(&self, domain: &str) -> Option<IocMatch> {
-        let parts: Vec<&str> = domain.split('.').rev().collect();
-        let mut current = &self.root;
-        let mut last_match: Option<IocMatch> = None;
-        
-        for part in parts {
-            let part_lower = part.to_lowercase();
-            
-            if let Some(node) = current.children.get(&part_lower) {
-                if node.is_terminal {
-                    last_match = node.match_info.clone();
+    pub fn clear(&self) {
+        *self.root.write() = DomainNode::new();
+    }
+}
+
+impl<T: Clone> Default for DomainSuffixTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn split_wildcard(domain: &str) -> (String, bool) {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    match domain.strip_prefix("*.") {
+        Some(rest) => (rest.to_string(), true),
+        None => (domain, false),
+    }
+}
+
+/// Longest-prefix-match trie for CIDR indicators
+///
+/// Binary radix trie over address bits; IPv4 and IPv6 live in separate
+/// roots so a v4 prefix never covers a v6 address.
+pub struct CidrTrie<T = IocMatch> {
+    v4: parking_lot::RwLock<PrefixTree<T>>,
+    v6: parking_lot::RwLock<PrefixTree<T>>,
+}
+
+struct PrefixTree<T> {
+    nodes: Vec<PrefixNode<T>>,
+    len: usize,
+}
+
+struct PrefixNode<T> {
+    children: [Option<u32>; 2],
+    value: Option<T>,
+}
+
+impl<T> PrefixTree<T> {
+    fn new() -> Self {
+        Self {
+            nodes: vec![PrefixNode { children: [None, None], value: None }],
+            len: 0,
+        }
+    }
+}
+
+impl<T: Clone> CidrTrie<T> {
+    pub fn new() -> Self {
+        Self {
+            v4: parking_lot::RwLock::new(PrefixTree::new()),
+            v6: parking_lot::RwLock::new(PrefixTree::new()),
+        }
+    }
+    
+    /// Insert a network; the address is masked to its prefix
+    pub fn insert(&self, network: IpAddr, prefix_len: u8, value: T) {
+        let (bits, width) = addr_bits(network);
+        let prefix_len = prefix_len.min(width);
+        let mut tree = self.tree(network).write();
+        
+        let mut idx = 0usize;
+        for i in 0..prefix_len {
+            let bit = bit_at(bits, width, i);
+            idx = match tree.nodes[idx].children[bit] {
+                Some(next) => next as usize,
+                None => {
+                    let next = tree.nodes.len();
+                    tree.nodes.push(PrefixNode { children: [None, None], value: None });
+                    tree.nodes[idx].children[bit] = Some(next as u32);
+                    next
                 }
-                // Continue traversal
-            } else {
+            };
+        }
+        
+        if tree.nodes[idx].value.replace(value).is_none() {
+            tree.len += 1;
+        }
+    }
+    
+    /// Insert a network in `addr/len` notation; a bare address is a host route
+    pub fn insert_str(&self, cidr: &str, value: T) -> bool {
+        match parse_cidr(cidr) {
+            Some((network, prefix_len)) => {
+                self.insert(network, prefix_len, value);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Remove a network
+    pub fn remove(&self, network: IpAddr, prefix_len: u8) -> Option<T> {
+        let (bits, width) = addr_bits(network);
+        let prefix_len = prefix_len.min(width);
+        let mut tree = self.tree(network).write();
+        
+        let mut idx = 0usize;
+        for i in 0..prefix_len {
+            idx = tree.nodes[idx].children[bit_at(bits, width, i)]? as usize;
+        }
+        
+        let removed = tree.nodes[idx].value.take();
+        if removed.is_some() {
+            tree.len -= 1;
+        }
+        removed
+    }
+    
+    /// Most specific network containing the address
+    pub fn longest_match(&self, ip: IpAddr) -> Option<T> {
+        let (bits, width) = addr_bits(ip);
+        let tree = self.tree(ip).read();
+        
+        // Walk without collecting so the hot path never allocates
+        let mut idx = 0usize;
+        let mut best = None;
+        for i in 0..=width {
+            if tree.nodes[idx].value.is_some() {
+                best = Some(idx);
+            }
+            if i == width {
                 break;
             }
+            match tree.nodes[idx].children[bit_at(bits, width, i)] {
+                Some(next) => idx = next as usize,
+                None => break,
+            }
+        }
+        best.and_then(|idx| tree.nodes[idx].value.clone())
+    }
+    
+    /// All networks containing the address, most specific first
+    pub fn covering(&self, ip: IpAddr) -> Vec<T> {
+        let (bits, width) = addr_bits(ip);
+        let tree = self.tree(ip).read();
+        let mut matches = Vec::new();
+        
+        let mut idx = 0usize;
+        for i in 0..=width {
+            if let Some(v) = &tree.nodes[idx].value {
+                matches.push(v.clone());
+            }
+            if i == width {
+                break;
+            }
+            match tree.nodes[idx].children[bit_at(bits, width, i)] {
+                Some(next) => idx = next as usize,
+                None => break,
+            }
         }
         
-        last_match
+        matches.reverse();
+        matches
+    }
+    
+    pub fn len(&self) -> usize {
+        self.v4.read().len + self.v6.read().len
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    pub fn clear(&self) {
+        *self.v4.write() = PrefixTree::new();
+        *self.v6.write() = PrefixTree::new();
+    }
+    
+    fn tree(&self, ip: IpAddr) -> &parking_lot::RwLock<PrefixTree<T>> {
+        match ip {
+            IpAddr::V4(_) => &self.v4,
+            IpAddr::V6(_) => &self.v6,
+        }
     }
 }
 
-impl Default for DomainSuffixTree {
+impl<T: Clone> Default for CidrTrie<T> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Parse `addr/len`; a bare address yields a full-length prefix
+pub fn parse_cidr(cidr: &str) -> Option<(IpAddr, u8)> {
+    let (addr, len) = match cidr.trim().split_once('/') {
+        Some((addr, len)) => (addr, Some(len)),
+        None => (cidr.trim(), None),
+    };
+    
+    let network: IpAddr = addr.parse().ok()?;
+    let width = addr_bits(network).1;
+    let prefix_len = match len {
+        Some(len) => len.parse::<u8>().ok().filter(|l| *l <= width)?,
+        None => width,
+    };
+    
+    Some((network, prefix_len))
+}
+
+fn addr_bits(ip: IpAddr) -> (u128, u8) {
+    match ip {
+        IpAddr::V4(v4) => (u128::from(u32::from(v4)), 32),
+        IpAddr::V6(v6) => (u128::from(v6), 128),
+    }
+}
+
+fn bit_at(bits: u128, width: u8, i: u8) -> usize {
+    ((bits >> (width - 1 - i)) & 1) as usize
+}
+
 /// IOC match result
 #[derive(Debug, Clone)]
 pub struct IocMatch {
@@ -248,6 +490,7 @@ impl IocMatchingEngine {
             url_map: dashmap::DashMap::new(),
             hash_map: dashmap::DashMap::new(),
             domain_suffixes: DomainSuffixTree::new(),
+            cidr_trie: CidrTrie::new(),
             stats: MatchingStats::default(),
         }
     }
@@ -267,10 +510,18 @@ impl IocMatchingEngine {
                     self.ip_map.insert(ip_bytes, match_info);
                 }
             }
+            IocType::Cidr => {
+                self.cidr_trie.insert_str(&indicator.value, match_info);
+            }
             IocType::Domain => {
                 let domain_lower = indicator.value.to_lowercase();
+                if domain_lower.starts_with("*.") {
+                    self.domain_suffixes.insert(&domain_lower, match_info);
+                    return;
+                }
+                // Blocking a domain blocks its subdomains too
                 self.domain_bloom.insert(domain_lower.as_bytes());
-                self.domain_suffixes.insert(&domain_lower, match_info.clone());
+                self.domain_suffixes.insert(&format!("*.{}", domain_lower), match_info.clone());
                 self.domain_map.insert(domain_lower, match_info);
             }
             IocType::Url => {
@@ -299,19 +550,23 @@ impl IocMatchingEngine {
             IpAddr::V6(v6) => u128::from(v6),
         };
         
-        // Fast bloom filter check
-        if !self.ip_bloom.contains(&ip_bytes.to_be_bytes()) {
-            return None;
+        // Fast bloom filter check, then exact lookup
+        if self.ip_bloom.contains(&ip_bytes.to_be_bytes()) {
+            if let Some(m) = self.ip_map.get(&ip_bytes) {
+                self.stats.lookups_hit.fetch_add(1, Ordering::Relaxed);
+                return Some(m.clone());
+            }
+            
+            // Bloom filter false positive
+            self.stats.bloom_false_positives.fetch_add(1, Ordering::Relaxed);
         }
         
-        // Exact lookup
-        if let Some(m) = self.ip_map.get(&ip_bytes) {
+        // Covering network
+        if let Some(m) = self.cidr_trie.longest_match(ip) {
             self.stats.lookups_hit.fetch_add(1, Ordering::Relaxed);
-            return Some(m.clone());
+            return Some(m);
         }
         
-        // Bloom filter false positive
-        self.stats.bloom_false_positives.fetch_add(1, Ordering::Relaxed);
         None
     }
     
@@ -332,24 +587,12 @@ impl IocMatchingEngine {
             }
         }
         
-        // Check parent domains using suffix tree
+        // Check parent domains and wildcards using suffix tree
         if let Some(m) = self.domain_suffixes.search(&domain_lower) {
             self.stats.lookups_hit.fetch_add(1, Ordering::Relaxed);
             return Some(m);
         }
         
-        // Manual parent domain check
-        let parts: Vec<&str> = domain_lower.split('.').collect();
-        for i in 1..parts.len() {
-            let parent = parts[i..].join(".");
-            if self.domain_bloom.contains(parent.as_bytes()) {
-                if let Some(m) = self.domain_map.get(&parent) {
-                    self.stats.lookups_hit.fetch_add(1, Ordering::Relaxed);
-                    return Some(m.clone());
-                }
-            }
-        }
-        
         None
     }
     
@@ -441,6 +684,8 @@ impl IocMatchingEngine {
         self.domain_map.clear();
         self.url_map.clear();
         self.hash_map.clear();
+        self.domain_suffixes.clear();
+        self.cidr_trie.clear();
     }
 }
 
@@ -541,4 +786,39 @@ mod tests {
         // No match
         assert!(engine.check_domain("safe.com").is_none());
     }
+    
+    #[test]
+    fn test_cidr_longest_prefix_match() {
+        let trie: CidrTrie<&str> = CidrTrie::new();
+        assert!(trie.insert_str("10.0.0.0/8", "wide"));
+        assert!(trie.insert_str("10.1.2.0/24", "narrow"));
+        assert!(trie.insert_str("2001:db8::/32", "v6"));
+        assert!(!trie.insert_str("10.0.0.0/33", "bad"));
+        
+        assert_eq!(trie.longest_match("10.1.2.77".parse().unwrap()), Some("narrow"));
+        assert_eq!(trie.covering("10.1.2.77".parse().unwrap()), vec!["narrow", "wide"]);
+        assert_eq!(trie.longest_match("10.9.9.9".parse().unwrap()), Some("wide"));
+        assert_eq!(trie.longest_match("11.0.0.1".parse().unwrap()), None);
+        assert_eq!(trie.longest_match("2001:db8::1".parse().unwrap()), Some("v6"));
+        
+        trie.remove("10.1.2.0".parse().unwrap(), 24);
+        assert_eq!(trie.longest_match("10.1.2.77".parse().unwrap()), Some("wide"));
+        assert_eq!(trie.len(), 2);
+    }
+    
+    #[test]
+    fn test_wildcard_domains() {
+        let tree: DomainSuffixTree<&str> = DomainSuffixTree::new();
+        tree.insert("*.evil.com", "wildcard");
+        tree.insert("login.evil.com", "exact");
+        
+        assert_eq!(tree.search("sub.evil.com"), Some("wildcard"));
+        assert_eq!(tree.search("a.b.EVIL.com"), Some("wildcard"));
+        assert_eq!(tree.search("login.evil.com"), Some("exact"));
+        assert_eq!(tree.search("evil.com"), None);
+        assert_eq!(tree.search("notevil.com"), None);
+        
+        assert_eq!(tree.remove("*.evil.com"), Some("wildcard"));
+        assert_eq!(tree.search("sub.evil.com"), None);
+    }
 }
@@ -1,9 +1,13 @@
 //! DNS Sinkhole Integration
 //!
-//! Block malicious domains at DNS level.
+//! Block malicious domains at DNS level. Sinkholed names resolve to
+//! controlled addresses from a per-tenant pool, and every client that asks
+//! for one is reported to the SOC as a likely-infected host.
 
 use crate::{Indicator, IocType};
 use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
 
 /// DNS Sinkhole for blocking malicious domains
 pub struct DnsSinkhole {
@@ -17,6 +21,18 @@ pub struct DnsSinkhole {
     sinkhole_ip: String,
     /// HTTP client
     client: reqwest::Client,
+    /// Address pools answered to each tenant's clients
+    tenant_pools: dashmap::DashMap<String, SinkholePool>,
+    /// Client networks owned by each tenant
+    tenant_networks: parking_lot::RwLock<Vec<(ipnetwork::IpNetwork, String)>>,
+    /// Clients seen resolving sinkholed domains, keyed by (client, entry domain)
+    victims: dashmap::DashMap<(IpAddr, String), VictimRecord>,
+    /// SOC event channel for victim sightings
+    events: Option<tokio::sync::mpsc::Sender<sase_soc::SecurityEvent>>,
+    /// Minimum time between repeated events for the same victim and domain
+    victim_event_interval: chrono::Duration,
+    /// TTL on synthesized answers
+    answer_ttl: u32,
     /// Statistics
     stats: SinkholeStats,
 }
@@ -31,7 +47,16 @@ pub struct SinkholeEntry {
     pub hit_count: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl SinkholeEntry {
+    pub fn is_active(&self) -> bool {
+        match self.expires_at {
+            Some(expires) => expires > chrono::Utc::now(),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum SinkholeCategory {
     Malware,
     Phishing,
@@ -47,6 +72,75 @@ pub struct SinkholeStats {
     pub domains_blocked: std::sync::atomic::AtomicU64,
     pub queries_blocked: std::sync::atomic::AtomicU64,
     pub whitelist_hits: std::sync::atomic::AtomicU64,
+    pub victims_reported: std::sync::atomic::AtomicU64,
+}
+
+/// Controlled addresses that sinkholed names resolve to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SinkholePool {
+    pub ipv4: Vec<Ipv4Addr>,
+    pub ipv6: Vec<Ipv6Addr>,
+}
+
+impl SinkholePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn with_ipv4(mut self, ip: Ipv4Addr) -> Self {
+        self.ipv4.push(ip);
+        self
+    }
+    
+    pub fn with_ipv6(mut self, ip: Ipv6Addr) -> Self {
+        self.ipv6.push(ip);
+        self
+    }
+    
+    /// IPv4 answer for a domain. Stable per domain so callbacks to the
+    /// sinkhole host can be traced back to the name that was resolved.
+    pub fn ipv4_for(&self, domain: &str) -> Option<Ipv4Addr> {
+        pick(&self.ipv4, domain)
+    }
+    
+    /// IPv6 answer for a domain
+    pub fn ipv6_for(&self, domain: &str) -> Option<Ipv6Addr> {
+        pick(&self.ipv6, domain)
+    }
+    
+    fn from_ip(ip: &str) -> Self {
+        match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(v4)) => Self::new().with_ipv4(v4),
+            Ok(IpAddr::V6(v6)) => Self::new().with_ipv6(v6),
+            Err(_) => Self::new().with_ipv4(Ipv4Addr::UNSPECIFIED),
+        }
+    }
+}
+
+/// A client that resolved a sinkholed domain
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VictimRecord {
+    pub client_ip: IpAddr,
+    /// Blocklist entry that matched
+    pub domain: String,
+    /// Name as queried (may be a subdomain of `domain`)
+    pub last_query: String,
+    pub category: SinkholeCategory,
+    pub tenant_id: Option<String>,
+    pub first_seen: chrono::DateTime<chrono::Utc>,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    pub queries: u64,
+    #[serde(skip)]
+    last_reported: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Answer for a sinkholed name
+#[derive(Debug, Clone)]
+pub struct SinkholeResolution {
+    pub entry: SinkholeEntry,
+    pub tenant_id: Option<String>,
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
 }
 
 impl DnsSinkhole {
@@ -57,6 +151,12 @@ impl DnsSinkhole {
             whitelist: default_whitelist(),
             sinkhole_ip: "0.0.0.0".to_string(),
             client: reqwest::Client::new(),
+            tenant_pools: dashmap::DashMap::new(),
+            tenant_networks: parking_lot::RwLock::new(Vec::new()),
+            victims: dashmap::DashMap::new(),
+            events: None,
+            victim_event_interval: chrono::Duration::hours(1),
+            answer_ttl: 300,
             stats: SinkholeStats::default(),
         }
     }
@@ -73,6 +173,139 @@ impl DnsSinkhole {
         self
     }
     
+    /// Report victims to the SOC over this channel
+    pub fn with_event_sink(mut self, events: tokio::sync::mpsc::Sender<sase_soc::SecurityEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+    
+    /// Suppress repeat events for the same victim and domain within `interval`
+    pub fn with_victim_event_interval(mut self, interval: chrono::Duration) -> Self {
+        self.victim_event_interval = interval;
+        self
+    }
+    
+    /// TTL on sinkhole answers
+    pub fn with_answer_ttl(mut self, ttl_secs: u32) -> Self {
+        self.answer_ttl = ttl_secs;
+        self
+    }
+    
+    /// Set the address pool answered to a tenant's clients
+    pub fn set_tenant_pool(&self, tenant_id: &str, pool: SinkholePool) {
+        self.tenant_pools.insert(tenant_id.to_string(), pool);
+    }
+    
+    pub fn remove_tenant_pool(&self, tenant_id: &str) {
+        self.tenant_pools.remove(tenant_id);
+    }
+    
+    /// Attribute queries from a client network to a tenant
+    pub fn assign_network(&self, network: ipnetwork::IpNetwork, tenant_id: &str) {
+        let mut networks = self.tenant_networks.write();
+        networks.retain(|(n, _)| *n != network);
+        networks.push((network, tenant_id.to_string()));
+        // Most specific network wins
+        networks.sort_by_key(|(n, _)| std::cmp::Reverse(n.prefix()));
+    }
+    
+    /// Tenant owning a client address
+    pub fn tenant_for(&self, client: IpAddr) -> Option<String> {
+        self.tenant_networks.read().iter()
+            .find(|(network, _)| network.contains(client))
+            .map(|(_, tenant)| tenant.clone())
+    }
+    
+    /// Pool for a tenant, falling back to the global sinkhole IP
+    pub fn pool_for(&self, tenant_id: Option<&str>) -> SinkholePool {
+        tenant_id
+            .and_then(|t| self.tenant_pools.get(t).map(|p| p.clone()))
+            .unwrap_or_else(|| SinkholePool::from_ip(&self.sinkhole_ip))
+    }
+    
+    /// Resolve a query from `client`. Returns `None` when the domain is not
+    /// sinkholed; otherwise records the client as a victim.
+    pub fn resolve(&self, domain: &str, client: IpAddr) -> Option<SinkholeResolution> {
+        let entry = self.should_block(domain)?;
+        let tenant_id = self.tenant_for(client);
+        let pool = self.pool_for(tenant_id.as_deref());
+        
+        self.record_victim(client, tenant_id.clone(), &entry, domain);
+        
+        Some(SinkholeResolution {
+            ipv4: pool.ipv4_for(&entry.domain),
+            ipv6: pool.ipv6_for(&entry.domain),
+            tenant_id,
+            entry,
+        })
+    }
+    
+    /// Answer a raw DNS query if it names a sinkholed domain
+    pub fn answer(&self, packet: &[u8], client: IpAddr) -> Option<Vec<u8>> {
+        let query = parse_query(packet)?;
+        let resolution = self.resolve(&query.name, client)?;
+        
+        let answers: Vec<IpAddr> = match query.qtype {
+            TYPE_A => resolution.ipv4.map(IpAddr::V4).into_iter().collect(),
+            TYPE_AAAA => resolution.ipv6.map(IpAddr::V6).into_iter().collect(),
+            TYPE_ANY => resolution.ipv4.map(IpAddr::V4).into_iter()
+                .chain(resolution.ipv6.map(IpAddr::V6))
+                .collect(),
+            // NODATA for other types so resolvers don't fall through
+            _ => Vec::new(),
+        };
+        
+        Some(build_response(&query, RCODE_NOERROR, &answers, self.answer_ttl))
+    }
+    
+    /// Clients seen resolving sinkholed domains, most recent first
+    pub fn victims(&self) -> Vec<VictimRecord> {
+        let mut victims: Vec<_> = self.victims.iter().map(|v| v.clone()).collect();
+        victims.sort_by_key(|v| std::cmp::Reverse(v.last_seen));
+        victims
+    }
+    
+    fn record_victim(&self, client: IpAddr, tenant_id: Option<String>, entry: &SinkholeEntry, queried: &str) {
+        use std::sync::atomic::Ordering;
+        
+        let now = chrono::Utc::now();
+        let mut record = self.victims.entry((client, entry.domain.clone()))
+            .or_insert_with(|| VictimRecord {
+                client_ip: client,
+                domain: entry.domain.clone(),
+                last_query: String::new(),
+                category: entry.category,
+                tenant_id: tenant_id.clone(),
+                first_seen: now,
+                last_seen: now,
+                queries: 0,
+                last_reported: None,
+            });
+        record.last_query = normalize_domain(queried);
+        record.last_seen = now;
+        record.queries += 1;
+        record.tenant_id = tenant_id;
+        
+        let due = match record.last_reported {
+            Some(at) => now - at >= self.victim_event_interval,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        
+        let Some(events) = &self.events else {
+            return;
+        };
+        match events.try_send(victim_event(&record, entry)) {
+            Ok(()) => {
+                record.last_reported = Some(now);
+                self.stats.victims_reported.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => tracing::warn!("Dropping sinkhole victim event for {}: {}", client, e),
+        }
+    }
+    
     /// Check if domain should be blocked
     pub fn should_block(&self, domain: &str) -> Option<SinkholeEntry> {
         use std::sync::atomic::Ordering;
@@ -86,7 +319,7 @@ impl DnsSinkhole {
         }
         
         // Check exact match
-        if let Some(mut entry) = self.blocklist.get_mut(&normalized) {
+        if let Some(mut entry) = self.blocklist.get_mut(&normalized).filter(|e| e.is_active()) {
             entry.hit_count += 1;
             self.stats.queries_blocked.fetch_add(1, Ordering::Relaxed);
             return Some(entry.clone());
@@ -96,7 +329,7 @@ impl DnsSinkhole {
         let parts: Vec<&str> = normalized.split('.').collect();
         for i in 1..parts.len() {
             let parent = parts[i..].join(".");
-            if let Some(mut entry) = self.blocklist.get_mut(&parent) {
+            if let Some(mut entry) = self.blocklist.get_mut(&parent).filter(|e| e.is_active()) {
                 entry.hit_count += 1;
                 self.stats.queries_blocked.fetch_add(1, Ordering::Relaxed);
                 return Some(entry.clone());
@@ -108,6 +341,15 @@ impl DnsSinkhole {
     
     /// Add domain to blocklist
     pub fn block(&self, domain: &str, reason: &str, category: SinkholeCategory) {
+        self.insert(domain, reason, category, None);
+    }
+    
+    /// Add domain to blocklist until `ttl` elapses
+    pub fn block_for(&self, domain: &str, reason: &str, category: SinkholeCategory, ttl: chrono::Duration) {
+        self.insert(domain, reason, category, Some(chrono::Utc::now() + ttl));
+    }
+    
+    fn insert(&self, domain: &str, reason: &str, category: SinkholeCategory, expires_at: Option<chrono::DateTime<chrono::Utc>>) {
         use std::sync::atomic::Ordering;
         
         let normalized = normalize_domain(domain);
@@ -117,7 +359,7 @@ impl DnsSinkhole {
             reason: reason.to_string(),
             category,
             added_at: chrono::Utc::now(),
-            expires_at: None,
+            expires_at,
             hit_count: 0,
         };
         
@@ -125,10 +367,22 @@ impl DnsSinkhole {
         self.stats.domains_blocked.fetch_add(1, Ordering::Relaxed);
     }
     
-    /// Remove domain from blocklist
-    pub fn unblock(&self, domain: &str) {
+    /// Remove domain from blocklist; true if it was present
+    pub fn unblock(&self, domain: &str) -> bool {
         let normalized = normalize_domain(domain);
-        self.blocklist.remove(&normalized);
+        self.blocklist.remove(&normalized).is_some()
+    }
+    
+    /// Drop entries whose TTL has elapsed
+    pub fn purge_expired(&self) -> usize {
+        let before = self.blocklist.len();
+        self.blocklist.retain(|_, entry| entry.is_active());
+        before - self.blocklist.len()
+    }
+    
+    /// Current blocklist entry for a domain, without counting a hit
+    pub fn entry(&self, domain: &str) -> Option<SinkholeEntry> {
+        self.blocklist.get(&normalize_domain(domain)).map(|e| e.clone())
     }
     
     /// Add domain to whitelist
@@ -272,7 +526,9 @@ impl DnsSinkhole {
             domains_blocked: self.stats.domains_blocked.load(Ordering::Relaxed),
             queries_blocked: self.stats.queries_blocked.load(Ordering::Relaxed),
             whitelist_hits: self.stats.whitelist_hits.load(Ordering::Relaxed),
+            victims_reported: self.stats.victims_reported.load(Ordering::Relaxed),
             blocklist_size: self.blocklist.len(),
+            victims_tracked: self.victims.len(),
         }
     }
     
@@ -313,7 +569,9 @@ pub struct SinkholeSnapshot {
     pub domains_blocked: u64,
     pub queries_blocked: u64,
     pub whitelist_hits: u64,
+    pub victims_reported: u64,
     pub blocklist_size: usize,
+    pub victims_tracked: usize,
 }
 
 #[derive(Debug)]
@@ -321,6 +579,7 @@ pub enum SinkholeError {
     NotConfigured,
     Network(String),
     SyncFailed(u16),
+    Io(String),
 }
 
 impl std::fmt::Display for SinkholeError {
@@ -329,8 +588,294 @@ impl std::fmt::Display for SinkholeError {
             Self::NotConfigured => write!(f, "Sinkhole not configured"),
             Self::Network(e) => write!(f, "Network error: {}", e),
             Self::SyncFailed(code) => write!(f, "Sync failed with status: {}", code),
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+// =============================================================================
+// DNS Responder
+// =============================================================================
+
+/// UDP DNS responder that answers sinkholed names and forwards the rest
+pub struct SinkholeResponder {
+    sinkhole: Arc<DnsSinkhole>,
+    /// Resolver for names that are not sinkholed; REFUSED when unset
+    upstream: Option<SocketAddr>,
+    upstream_timeout: std::time::Duration,
+}
+
+impl SinkholeResponder {
+    pub fn new(sinkhole: Arc<DnsSinkhole>) -> Self {
+        Self {
+            sinkhole,
+            upstream: None,
+            upstream_timeout: std::time::Duration::from_secs(2),
+        }
+    }
+    
+    pub fn with_upstream(mut self, upstream: SocketAddr) -> Self {
+        self.upstream = Some(upstream);
+        self
+    }
+    
+    pub fn with_upstream_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.upstream_timeout = timeout;
+        self
+    }
+    
+    /// Serve queries on `addr` until the socket fails
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) -> Result<(), SinkholeError> {
+        let socket = Arc::new(
+            tokio::net::UdpSocket::bind(addr).await
+                .map_err(|e| SinkholeError::Io(e.to_string()))?,
+        );
+        tracing::info!("DNS sinkhole listening on {}", addr);
+        
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, peer) = socket.recv_from(&mut buf).await
+                .map_err(|e| SinkholeError::Io(e.to_string()))?;
+            let packet = buf[..len].to_vec();
+            let responder = self.clone();
+            let socket = socket.clone();
+            
+            tokio::spawn(async move {
+                if let Some(response) = responder.handle(&packet, peer.ip()).await {
+                    if let Err(e) = socket.send_to(&response, peer).await {
+                        tracing::debug!("Failed to answer {}: {}", peer, e);
+                    }
+                }
+            });
+        }
+    }
+    
+    /// Response for one query, `None` if the packet should be dropped
+    pub async fn handle(&self, packet: &[u8], client: IpAddr) -> Option<Vec<u8>> {
+        let Some(query) = parse_query(packet) else {
+            return error_response(packet, RCODE_FORMERR);
+        };
+        
+        if let Some(response) = self.sinkhole.answer(packet, client) {
+            return Some(response);
+        }
+        
+        match self.upstream {
+            Some(upstream) => match self.forward(packet, upstream).await {
+                Ok(response) => Some(response),
+                Err(e) => {
+                    tracing::warn!("Upstream resolution of {} failed: {}", query.name, e);
+                    Some(build_response(&query, RCODE_SERVFAIL, &[], 0))
+                }
+            },
+            None => Some(build_response(&query, RCODE_REFUSED, &[], 0)),
+        }
+    }
+    
+    async fn forward(&self, packet: &[u8], upstream: SocketAddr) -> Result<Vec<u8>, SinkholeError> {
+        let bind: SocketAddr = if upstream.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = tokio::net::UdpSocket::bind(bind).await
+            .map_err(|e| SinkholeError::Io(e.to_string()))?;
+        socket.send_to(packet, upstream).await
+            .map_err(|e| SinkholeError::Io(e.to_string()))?;
+        
+        let mut buf = [0u8; 4096];
+        let len = tokio::time::timeout(self.upstream_timeout, socket.recv(&mut buf)).await
+            .map_err(|_| SinkholeError::Network("upstream timeout".to_string()))?
+            .map_err(|e| SinkholeError::Io(e.to_string()))?;
+        Ok(buf[..len].to_vec())
+    }
+}
+
+/// Feed victim events from the sinkhole into the SOC pipeline until every
+/// sender is dropped
+pub async fn forward_to_soc(
+    mut events: tokio::sync::mpsc::Receiver<sase_soc::SecurityEvent>,
+    platform: &sase_soc::SecurityOperationsPlatform,
+) {
+    while let Some(event) = events.recv().await {
+        platform.ingest_event(event).await;
+    }
+}
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+
+const RCODE_NOERROR: u8 = 0;
+const RCODE_FORMERR: u8 = 1;
+const RCODE_SERVFAIL: u8 = 2;
+const RCODE_REFUSED: u8 = 5;
+
+/// Single-question standard query
+struct DnsQuery<'a> {
+    id: u16,
+    flags: u16,
+    name: String,
+    qtype: u16,
+    /// Raw question section, echoed back in the response
+    question: &'a [u8],
+}
+
+fn parse_query(packet: &[u8]) -> Option<DnsQuery<'_>> {
+    if packet.len() < 12 {
+        return None;
+    }
+    let id = u16::from_be_bytes([packet[0], packet[1]]);
+    let flags = u16::from_be_bytes([packet[2], packet[3]]);
+    let qdcount = u16::from_be_bytes([packet[4], packet[5]]);
+    
+    // Queries only (QR=0), standard opcode, exactly one question
+    if flags & 0x8000 != 0 || (flags >> 11) & 0x0f != 0 || qdcount != 1 {
+        return None;
+    }
+    
+    let mut pos = 12;
+    let mut labels = Vec::new();
+    loop {
+        let len = *packet.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // No compression pointers in a question, labels max 63 octets
+        if len > 63 {
+            return None;
+        }
+        let label = packet.get(pos..pos + len)?;
+        labels.push(String::from_utf8_lossy(label).to_lowercase());
+        pos += len;
+        if pos - 12 > 255 {
+            return None;
         }
     }
+    
+    let fixed = packet.get(pos..pos + 4)?;
+    let qtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+    
+    Some(DnsQuery {
+        id,
+        flags,
+        name: labels.join("."),
+        qtype,
+        question: &packet[12..pos + 4],
+    })
+}
+
+fn build_response(query: &DnsQuery, rcode: u8, answers: &[IpAddr], ttl: u32) -> Vec<u8> {
+    // QR, AA, RA set; RD echoed from the query
+    let flags = 0x8000 | 0x0400 | 0x0080 | (query.flags & 0x0100) | rcode as u16;
+    
+    let mut out = Vec::with_capacity(12 + query.question.len() + answers.len() * 28);
+    out.extend_from_slice(&query.id.to_be_bytes());
+    out.extend_from_slice(&flags.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(query.question);
+    
+    for answer in answers {
+        // Name is a pointer to the question at offset 12
+        out.extend_from_slice(&[0xc0, 0x0c]);
+        let (rtype, rdata) = match answer {
+            IpAddr::V4(v4) => (TYPE_A, v4.octets().to_vec()),
+            IpAddr::V6(v6) => (TYPE_AAAA, v6.octets().to_vec()),
+        };
+        out.extend_from_slice(&rtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out.extend_from_slice(&ttl.to_be_bytes());
+        out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        out.extend_from_slice(&rdata);
+    }
+    
+    out
+}
+
+/// Header-only error response; dropped if there is no header to answer
+fn error_response(packet: &[u8], rcode: u8) -> Option<Vec<u8>> {
+    let header = packet.get(..12)?;
+    if header[2] & 0x80 != 0 {
+        return None;
+    }
+    let mut out = vec![0u8; 12];
+    out[..2].copy_from_slice(&header[..2]);
+    out[2] = 0x80 | (header[2] & 0x79);
+    out[3] = 0x80 | rcode;
+    Some(out)
+}
+
+fn victim_event(record: &VictimRecord, entry: &SinkholeEntry) -> sase_soc::SecurityEvent {
+    use sase_soc::{EventSource, EventType, IndicatorType, Severity};
+    
+    let severity = match entry.category {
+        SinkholeCategory::C2 => Severity::Critical,
+        SinkholeCategory::Malware | SinkholeCategory::Phishing => Severity::High,
+        SinkholeCategory::Custom => Severity::Medium,
+        SinkholeCategory::Spam | SinkholeCategory::Adware | SinkholeCategory::Tracker => Severity::Low,
+    };
+    let event_type = match entry.category {
+        SinkholeCategory::C2 | SinkholeCategory::Malware => EventType::MalwareDetected,
+        _ => EventType::SuspiciousTraffic,
+    };
+    
+    sase_soc::SecurityEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_type,
+        severity,
+        source: EventSource {
+            system: "threat-intel".to_string(),
+            component: "dns-sinkhole".to_string(),
+            host: None,
+            ip: Some(record.client_ip.to_string()),
+        },
+        timestamp: record.last_seen,
+        description: format!(
+            "{} resolved sinkholed domain {} ({:?}: {})",
+            record.client_ip, record.last_query, entry.category, entry.reason
+        ),
+        raw_data: serde_json::json!({
+            "client_ip": record.client_ip.to_string(),
+            "query": record.last_query,
+            "sinkhole_domain": entry.domain,
+            "category": entry.category,
+            "reason": entry.reason,
+            "queries": record.queries,
+            "first_seen": record.first_seen,
+        }),
+        indicators: vec![
+            sase_soc::Indicator {
+                indicator_type: IndicatorType::IpAddress,
+                value: record.client_ip.to_string(),
+                confidence: 0.8,
+                context: Some("sinkhole victim".to_string()),
+            },
+            sase_soc::Indicator {
+                indicator_type: IndicatorType::Domain,
+                value: entry.domain.clone(),
+                confidence: 1.0,
+                context: Some(entry.reason.clone()),
+            },
+        ],
+        tags: vec!["sinkhole".to_string(), format!("sinkhole:{:?}", entry.category).to_lowercase()],
+        tenant_id: record.tenant_id.clone(),
+    }
+}
+
+fn pick<T: Copy>(pool: &[T], domain: &str) -> Option<T> {
+    if pool.is_empty() {
+        return None;
+    }
+    // FNV-1a; stable across processes unlike the std hasher
+    let hash = domain.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    Some(pool[(hash % pool.len() as u64) as usize])
 }
 
 fn normalize_domain(domain: &str) -> String {
@@ -390,4 +935,56 @@ mod tests {
         assert!(sinkhole.should_block("google.com").is_none());
         assert!(sinkhole.should_block("mail.google.com").is_none());
     }
+    
+    #[tokio::test]
+    async fn test_responder_answers_and_reports_victims() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let sinkhole = Arc::new(
+            DnsSinkhole::new()
+                .with_sinkhole_ip("192.0.2.1")
+                .with_event_sink(tx),
+        );
+        sinkhole.block("c2.example", "Beacon endpoint", SinkholeCategory::C2);
+        sinkhole.set_tenant_pool("acme", SinkholePool::new()
+            .with_ipv4("198.51.100.7".parse().unwrap())
+            .with_ipv6("2001:db8::7".parse().unwrap()));
+        sinkhole.assign_network("10.1.0.0/16".parse().unwrap(), "acme");
+        
+        // A query for beacon.c2.example from an acme client
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in ["beacon", "c2", "example"] {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.extend_from_slice(&[0, 0, 1, 0, 1]);
+        
+        let responder = SinkholeResponder::new(sinkhole.clone());
+        let victim: IpAddr = "10.1.2.3".parse().unwrap();
+        let response = responder.handle(&query, victim).await.unwrap();
+        
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        assert_eq!(response[3] & 0x0f, RCODE_NOERROR);
+        assert_eq!(u16::from_be_bytes([response[6], response[7]]), 1);
+        assert_eq!(&response[response.len() - 4..], &[198, 51, 100, 7]);
+        
+        // Outside any tenant network the global sinkhole IP is used
+        let response = responder.handle(&query, "203.0.113.9".parse().unwrap()).await.unwrap();
+        assert_eq!(&response[response.len() - 4..], &[192, 0, 2, 1]);
+        
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(event.severity, sase_soc::Severity::Critical);
+        assert_eq!(event.source.ip.as_deref(), Some("10.1.2.3"));
+        assert!(rx.try_recv().is_ok());
+        
+        // Repeat queries inside the interval are counted, not re-reported
+        responder.handle(&query, victim).await.unwrap();
+        assert!(rx.try_recv().is_err());
+        assert_eq!(sinkhole.victims().iter().find(|v| v.client_ip == victim).unwrap().queries, 2);
+        
+        // Unsinkholed names are refused without an upstream
+        assert!(sinkhole.unblock("c2.example"));
+        let response = responder.handle(&query, victim).await.unwrap();
+        assert_eq!(response[3] & 0x0f, RCODE_REFUSED);
+    }
 }
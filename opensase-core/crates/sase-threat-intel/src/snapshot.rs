@@ -0,0 +1,726 @@
+//! Cold-start Snapshots
+//!
+//! A new PoP needs the full distributable indicator set before it can
+//! enforce anything, and replaying millions of incremental changes is too
+//! slow. The publisher periodically builds one compressed, signed snapshot
+//! per IoC type. A PoP downloads each snapshot with ranged requests
+//! (resuming after interruptions), verifies size, digest and signature,
+//! activates it, and then pulls incremental changes from the distribution
+//! journal starting at the snapshot's sequence.
+//!
+//! Like the TAXII server this is transport-agnostic: mount
+//! `SnapshotPublisher::handle` behind the platform's HTTP listener.
+
+use crate::distribution::IndicatorChange;
+use crate::{Indicator, IocType, ThreatIntelService};
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+pub const SNAPSHOT_MEDIA_TYPE: &str = "application/x-ndjson+gzip";
+
+// =============================================================================
+// Manifests
+// =============================================================================
+
+/// Snapshot publishing settings
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    /// Time between rebuilds
+    pub interval: std::time::Duration,
+    /// Snapshots kept per type so downloads in flight survive a rebuild
+    pub retain: usize,
+    /// Types snapshotted, one snapshot each
+    pub ioc_types: Vec<IocType>,
+    /// gzip level (0-9)
+    pub compression_level: u32,
+    /// Journal changes returned per incremental pull
+    pub max_changes_page: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            interval: std::time::Duration::from_secs(3600),
+            retain: 2,
+            ioc_types: vec![
+                IocType::IPv4,
+                IocType::IPv6,
+                IocType::Cidr,
+                IocType::Domain,
+                IocType::Url,
+                IocType::FileHashMd5,
+                IocType::FileHashSha1,
+                IocType::FileHashSha256,
+                IocType::Ja3Hash,
+                IocType::JarmHash,
+                IocType::SslCertHash,
+            ],
+            compression_level: 6,
+            max_changes_page: 10_000,
+        }
+    }
+}
+
+/// Signed description of one snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub id: String,
+    pub ioc_type: IocType,
+    /// Distribution sequence the snapshot was cut at; incremental pulls
+    /// resume after it
+    pub sequence: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub entries: usize,
+    /// Size of the compressed payload in bytes
+    pub size: u64,
+    /// Hex SHA-256 of the compressed payload
+    pub sha256: String,
+    /// Identifies the signing key
+    pub key_id: String,
+    /// Hex Ed25519 signature over the other fields
+    pub signature: String,
+}
+
+impl SnapshotManifest {
+    fn signing_payload(&self) -> Vec<u8> {
+        format!(
+            "opensase-snapshot-v1\n{}\n{:?}\n{}\n{}\n{}\n{}\n{}\n{}",
+            self.id,
+            self.ioc_type,
+            self.sequence,
+            self.created_at.timestamp(),
+            self.entries,
+            self.size,
+            self.sha256,
+            self.key_id,
+        ).into_bytes()
+    }
+    
+    /// Check the signature against a trusted key
+    pub fn verify_signature(&self, key: &VerifyingKey) -> Result<(), SnapshotError> {
+        if self.key_id != key_id(key) {
+            return Err(SnapshotError::UnknownKey(self.key_id.clone()));
+        }
+        let bytes: [u8; 64] = hex::decode(&self.signature).ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or(SnapshotError::BadSignature)?;
+        key.verify(&self.signing_payload(), &ed25519_dalek::Signature::from_bytes(&bytes))
+            .map_err(|_| SnapshotError::BadSignature)
+    }
+}
+
+/// Built snapshot held for serving
+#[derive(Debug)]
+pub struct Snapshot {
+    pub manifest: SnapshotManifest,
+    data: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Short stable identifier for a verifying key
+pub fn key_id(key: &VerifyingKey) -> String {
+    hex::encode(&Sha256::digest(key.as_bytes())[..8])
+}
+
+// =============================================================================
+// Publisher
+// =============================================================================
+
+/// HTTP request for snapshot or journal data
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotRequest {
+    pub method: String,
+    /// Path below the mount point, e.g. `/snapshots/{id}`
+    pub path: String,
+    /// Raw query string without the leading `?`
+    pub query: String,
+    /// `Range` header value
+    pub range: Option<String>,
+    /// `If-Range` header value
+    pub if_range: Option<String>,
+}
+
+/// Response to write back to the PoP
+#[derive(Debug, Clone)]
+pub struct SnapshotResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl SnapshotResponse {
+    fn json(status: u16, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: serde_json::to_vec(body).unwrap_or_default(),
+        }
+    }
+    
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+/// Snapshot listing returned to PoPs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotIndex {
+    /// Current distribution sequence
+    pub sequence: u64,
+    pub snapshots: Vec<SnapshotManifest>,
+}
+
+/// One page of journaled changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangesPage {
+    /// Current distribution sequence
+    pub sequence: u64,
+    pub changes: Vec<IndicatorChange>,
+    /// More changes are available after the last one returned
+    pub more: bool,
+}
+
+/// Builds, keeps and serves signed snapshots
+pub struct SnapshotPublisher {
+    service: Arc<ThreatIntelService>,
+    config: SnapshotConfig,
+    signing_key: SigningKey,
+    key_id: String,
+    /// Newest last
+    snapshots: parking_lot::RwLock<HashMap<IocType, VecDeque<Arc<Snapshot>>>>,
+}
+
+impl SnapshotPublisher {
+    pub fn new(service: Arc<ThreatIntelService>, signing_key: SigningKey) -> Self {
+        let key_id = key_id(&signing_key.verifying_key());
+        Self {
+            service,
+            config: SnapshotConfig::default(),
+            signing_key,
+            key_id,
+            snapshots: parking_lot::RwLock::new(HashMap::new()),
+        }
+    }
+    
+    pub fn with_config(mut self, config: SnapshotConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// Key PoPs must trust to activate snapshots
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+    
+    /// Rebuild every configured type
+    pub fn build_all(&self) -> Result<Vec<SnapshotManifest>, SnapshotError> {
+        self.config.ioc_types.iter()
+            .map(|t| self.build(*t))
+            .collect()
+    }
+    
+    /// Build a fresh snapshot of one type
+    pub fn build(&self, ioc_type: IocType) -> Result<SnapshotManifest, SnapshotError> {
+        // Cut the sequence first: changes racing the scan are replayed by
+        // the PoP's first incremental pull, and replaying is idempotent
+        let sequence = self.service.distributor().sequence();
+        let indicators = self.service.distributable(ioc_type);
+        
+        let mut encoder = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::new(self.config.compression_level.min(9)),
+        );
+        for indicator in &indicators {
+            serde_json::to_writer(&mut encoder, indicator)
+                .map_err(|e| SnapshotError::Encoding(e.to_string()))?;
+            encoder.write_all(b"\n").map_err(|e| SnapshotError::Encoding(e.to_string()))?;
+        }
+        let data = encoder.finish().map_err(|e| SnapshotError::Encoding(e.to_string()))?;
+        
+        let mut manifest = SnapshotManifest {
+            id: uuid::Uuid::new_v4().to_string(),
+            ioc_type,
+            sequence,
+            created_at: chrono::Utc::now(),
+            entries: indicators.len(),
+            size: data.len() as u64,
+            sha256: hex::encode(Sha256::digest(&data)),
+            key_id: self.key_id.clone(),
+            signature: String::new(),
+        };
+        manifest.signature = hex::encode(self.signing_key.sign(&manifest.signing_payload()).to_bytes());
+        
+        tracing::info!(
+            "Built {:?} snapshot {}: {} indicators, {} bytes at sequence {}",
+            ioc_type, manifest.id, manifest.entries, manifest.size, sequence
+        );
+        
+        let mut snapshots = self.snapshots.write();
+        let kept = snapshots.entry(ioc_type).or_default();
+        kept.push_back(Arc::new(Snapshot { manifest: manifest.clone(), data }));
+        while kept.len() > self.config.retain.max(1) {
+            kept.pop_front();
+        }
+        
+        Ok(manifest)
+    }
+    
+    /// Newest snapshot of each type
+    pub fn manifests(&self) -> Vec<SnapshotManifest> {
+        let snapshots = self.snapshots.read();
+        self.config.ioc_types.iter()
+            .filter_map(|t| snapshots.get(t)?.back().map(|s| s.manifest.clone()))
+            .collect()
+    }
+    
+    /// Any retained snapshot by id
+    pub fn get(&self, id: &str) -> Option<Arc<Snapshot>> {
+        self.snapshots.read().values()
+            .flat_map(|kept| kept.iter())
+            .find(|s| s.manifest.id == id)
+            .cloned()
+    }
+    
+    /// Rebuild on the configured interval
+    pub fn spawn_builder(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.interval);
+            loop {
+                ticker.tick().await;
+                let publisher = self.clone();
+                match tokio::task::spawn_blocking(move || publisher.build_all()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => tracing::warn!("Snapshot build failed: {}", e),
+                    Err(e) => tracing::warn!("Snapshot build task failed: {}", e),
+                }
+            }
+        })
+    }
+    
+    /// Route a request:
+    /// - `GET /snapshots` - newest manifest per type
+    /// - `GET /snapshots/{id}/manifest`
+    /// - `GET|HEAD /snapshots/{id}` - payload, honouring `Range`
+    /// - `GET /changes?since={seq}&limit={n}` - journal after a sequence
+    pub fn handle(&self, request: &SnapshotRequest) -> SnapshotResponse {
+        let head = request.method.eq_ignore_ascii_case("HEAD");
+        if !head && !request.method.eq_ignore_ascii_case("GET") {
+            return SnapshotResponse::error(405, "method not allowed");
+        }
+        
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let mut response = match segments.as_slice() {
+            ["snapshots"] => SnapshotResponse::json(200, &SnapshotIndex {
+                sequence: self.service.distributor().sequence(),
+                snapshots: self.manifests(),
+            }),
+            ["snapshots", id, "manifest"] => match self.get(id) {
+                Some(snapshot) => SnapshotResponse::json(200, &snapshot.manifest),
+                None => SnapshotResponse::error(404, "unknown snapshot"),
+            },
+            ["snapshots", id] => match self.get(id) {
+                Some(snapshot) => serve_range(&snapshot, request),
+                None => SnapshotResponse::error(404, "unknown snapshot"),
+            },
+            ["changes"] => self.changes(&request.query),
+            _ => SnapshotResponse::error(404, "not found"),
+        };
+        
+        if head {
+            response.body.clear();
+        }
+        response
+    }
+    
+    fn changes(&self, query: &str) -> SnapshotResponse {
+        let params: HashMap<&str, &str> = query.split('&')
+            .filter_map(|p| p.split_once('='))
+            .collect();
+        let Some(since) = params.get("since").and_then(|s| s.parse::<u64>().ok()) else {
+            return SnapshotResponse::error(400, "since is required");
+        };
+        let limit = params.get("limit")
+            .and_then(|l| l.parse::<usize>().ok())
+            .unwrap_or(self.config.max_changes_page)
+            .clamp(1, self.config.max_changes_page);
+        
+        let distributor = self.service.distributor();
+        let sequence = distributor.sequence();
+        match distributor.changes_since(since, limit) {
+            Some(changes) => {
+                let more = changes.last().map(|c| c.sequence < sequence).unwrap_or(false);
+                SnapshotResponse::json(200, &ChangesPage { sequence, changes, more })
+            }
+            // Journal no longer reaches back that far
+            None => SnapshotResponse::error(410, "sequence expired; download a fresh snapshot"),
+        }
+    }
+}
+
+fn serve_range(snapshot: &Snapshot, request: &SnapshotRequest) -> SnapshotResponse {
+    let total = snapshot.data.len() as u64;
+    let etag = format!("\"{}\"", snapshot.manifest.sha256);
+    
+    let mut headers = vec![
+        ("Accept-Ranges".to_string(), "bytes".to_string()),
+        ("ETag".to_string(), etag.clone()),
+        ("X-Snapshot-Sequence".to_string(), snapshot.manifest.sequence.to_string()),
+    ];
+    
+    // A stale If-Range validator means the client's partial copy is of a
+    // different payload, so it gets the whole thing
+    let range = request.range.as_deref()
+        .filter(|_| request.if_range.as_deref().map(|v| v == etag).unwrap_or(true));
+    
+    let (status, start, end) = match range.map(|r| parse_range(r, total)) {
+        None | Some(RangeSpec::Ignored) => (200, 0, total),
+        Some(RangeSpec::Unsatisfiable) => {
+            headers.push(("Content-Range".to_string(), format!("bytes */{}", total)));
+            return SnapshotResponse { status: 416, content_type: SNAPSHOT_MEDIA_TYPE, headers, body: Vec::new() };
+        }
+        Some(RangeSpec::Bytes(start, end)) => {
+            headers.push(("Content-Range".to_string(), format!("bytes {}-{}/{}", start, end - 1, total)));
+            (206, start, end)
+        }
+    };
+    
+    headers.push(("Content-Length".to_string(), (end - start).to_string()));
+    SnapshotResponse {
+        status,
+        content_type: SNAPSHOT_MEDIA_TYPE,
+        headers,
+        body: snapshot.data[start as usize..end as usize].to_vec(),
+    }
+}
+
+enum RangeSpec {
+    /// Half-open byte range
+    Bytes(u64, u64),
+    Unsatisfiable,
+    /// Malformed or multi-range; serve the full body
+    Ignored,
+}
+
+fn parse_range(header: &str, total: u64) -> RangeSpec {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return RangeSpec::Ignored;
+    };
+    if spec.contains(',') {
+        return RangeSpec::Ignored;
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return RangeSpec::Ignored;
+    };
+    
+    let (start, end) = match (first.trim(), last.trim()) {
+        ("", "") => return RangeSpec::Ignored,
+        // Suffix range: the final N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeSpec::Unsatisfiable,
+            Ok(n) => (total.saturating_sub(n), total),
+            Err(_) => return RangeSpec::Ignored,
+        },
+        (first, last) => {
+            let Ok(start) = first.parse::<u64>() else {
+                return RangeSpec::Ignored;
+            };
+            let end = match last {
+                "" => total,
+                last => match last.parse::<u64>() {
+                    Ok(l) if l >= start => (l + 1).min(total),
+                    _ => return RangeSpec::Ignored,
+                },
+            };
+            (start, end)
+        }
+    };
+    
+    if start >= total {
+        RangeSpec::Unsatisfiable
+    } else {
+        RangeSpec::Bytes(start, end)
+    }
+}
+
+// =============================================================================
+// PoP Side
+// =============================================================================
+
+/// Partially downloaded snapshot; resumable across restarts by persisting
+/// the manifest and the bytes received so far
+#[derive(Debug, Clone)]
+pub struct SnapshotDownload {
+    pub manifest: SnapshotManifest,
+    data: Vec<u8>,
+}
+
+/// Snapshot that passed verification, ready to activate
+#[derive(Debug, Clone)]
+pub struct VerifiedSnapshot {
+    pub manifest: SnapshotManifest,
+    pub indicators: Vec<Indicator>,
+}
+
+impl VerifiedSnapshot {
+    /// Sequence to pass as `since` for the first incremental pull
+    pub fn resume_after(&self) -> u64 {
+        self.manifest.sequence
+    }
+}
+
+impl SnapshotDownload {
+    pub fn new(manifest: SnapshotManifest) -> Self {
+        Self { manifest, data: Vec::new() }
+    }
+    
+    /// Resume with bytes already on disk
+    pub fn resume(manifest: SnapshotManifest, partial: Vec<u8>) -> Self {
+        Self { manifest, data: partial }
+    }
+    
+    pub fn received(&self) -> u64 {
+        self.data.len() as u64
+    }
+    
+    pub fn is_complete(&self) -> bool {
+        self.received() >= self.manifest.size
+    }
+    
+    pub fn partial(&self) -> &[u8] {
+        &self.data
+    }
+    
+    /// `Range` header for the next chunk, `None` once complete
+    pub fn next_range(&self, chunk_size: u64) -> Option<String> {
+        if self.is_complete() {
+            return None;
+        }
+        let end = (self.received() + chunk_size.max(1)).min(self.manifest.size) - 1;
+        Some(format!("bytes={}-{}", self.received(), end))
+    }
+    
+    /// Add a chunk that starts at byte `offset`
+    pub fn append(&mut self, offset: u64, chunk: &[u8]) -> Result<(), SnapshotError> {
+        if offset != self.received() {
+            return Err(SnapshotError::OutOfOrder { expected: self.received(), got: offset });
+        }
+        if self.received() + chunk.len() as u64 > self.manifest.size {
+            return Err(SnapshotError::SizeMismatch { expected: self.manifest.size, got: self.received() + chunk.len() as u64 });
+        }
+        self.data.extend_from_slice(chunk);
+        Ok(())
+    }
+    
+    /// Verify size, digest, signature and contents before activation
+    pub fn verify(self, key: &VerifyingKey) -> Result<VerifiedSnapshot, SnapshotError> {
+        let manifest = self.manifest;
+        if self.data.len() as u64 != manifest.size {
+            return Err(SnapshotError::SizeMismatch { expected: manifest.size, got: self.data.len() as u64 });
+        }
+        if hex::encode(Sha256::digest(&self.data)) != manifest.sha256 {
+            return Err(SnapshotError::DigestMismatch);
+        }
+        manifest.verify_signature(key)?;
+        
+        let decoder = std::io::BufReader::new(flate2::read::GzDecoder::new(self.data.as_slice()));
+        let mut indicators = Vec::with_capacity(manifest.entries);
+        for line in decoder.lines() {
+            let line = line.map_err(|e| SnapshotError::Encoding(e.to_string()))?;
+            if line.is_empty() {
+                continue;
+            }
+            let indicator: Indicator = serde_json::from_str(&line)
+                .map_err(|e| SnapshotError::Encoding(e.to_string()))?;
+            if indicator.ioc_type != manifest.ioc_type {
+                return Err(SnapshotError::Encoding(format!("{:?} entry in {:?} snapshot", indicator.ioc_type, manifest.ioc_type)));
+            }
+            indicators.push(indicator);
+        }
+        if indicators.len() != manifest.entries {
+            return Err(SnapshotError::Encoding(format!("expected {} entries, found {}", manifest.entries, indicators.len())));
+        }
+        
+        Ok(VerifiedSnapshot { manifest, indicators })
+    }
+}
+
+/// Download the rest of a snapshot from `{base_url}/snapshots/{id}` in
+/// ranged chunks. On error the bytes received so far are kept for a retry.
+pub async fn download_snapshot(
+    client: &reqwest::Client,
+    base_url: &str,
+    download: &mut SnapshotDownload,
+    chunk_size: u64,
+) -> Result<(), SnapshotError> {
+    let url = format!("{}/snapshots/{}", base_url.trim_end_matches('/'), download.manifest.id);
+    let etag = format!("\"{}\"", download.manifest.sha256);
+    
+    while let Some(range) = download.next_range(chunk_size) {
+        let response = client.get(&url)
+            .header("Range", range)
+            .header("If-Range", &etag)
+            .send()
+            .await
+            .map_err(|e| SnapshotError::Network(e.to_string()))?;
+        
+        let status = response.status().as_u16();
+        let body = response.bytes().await.map_err(|e| SnapshotError::Network(e.to_string()))?;
+        match status {
+            206 => download.append(download.received(), &body)?,
+            // Server ignored the range; take the full body
+            200 => {
+                *download = SnapshotDownload::new(download.manifest.clone());
+                download.append(0, &body)?;
+            }
+            404 | 410 => return Err(SnapshotError::NotFound(download.manifest.id.clone())),
+            other => return Err(SnapshotError::Network(format!("{} returned {}", url, other))),
+        }
+    }
+    
+    Ok(())
+}
+
+/// Pull journaled changes after `since` from `{base_url}/changes`
+pub async fn fetch_changes(
+    client: &reqwest::Client,
+    base_url: &str,
+    since: u64,
+    limit: usize,
+) -> Result<ChangesPage, SnapshotError> {
+    let url = format!("{}/changes?since={}&limit={}", base_url.trim_end_matches('/'), since, limit);
+    let response = client.get(&url).send().await
+        .map_err(|e| SnapshotError::Network(e.to_string()))?;
+    
+    match response.status().as_u16() {
+        200 => response.json().await.map_err(|e| SnapshotError::Encoding(e.to_string())),
+        410 => Err(SnapshotError::SequenceExpired(since)),
+        other => Err(SnapshotError::Network(format!("{} returned {}", url, other))),
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    NotFound(String),
+    Network(String),
+    Encoding(String),
+    OutOfOrder { expected: u64, got: u64 },
+    SizeMismatch { expected: u64, got: u64 },
+    DigestMismatch,
+    BadSignature,
+    UnknownKey(String),
+    /// Journal no longer holds changes after this sequence
+    SequenceExpired(u64),
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "Snapshot not found: {}", id),
+            Self::Network(e) => write!(f, "Network error: {}", e),
+            Self::Encoding(e) => write!(f, "Invalid snapshot data: {}", e),
+            Self::OutOfOrder { expected, got } => write!(f, "Chunk at offset {} (expected {})", got, expected),
+            Self::SizeMismatch { expected, got } => write!(f, "Snapshot size {} (expected {})", got, expected),
+            Self::DigestMismatch => write!(f, "Snapshot digest mismatch"),
+            Self::BadSignature => write!(f, "Snapshot signature invalid"),
+            Self::UnknownKey(id) => write!(f, "Snapshot signed by unknown key {}", id),
+            Self::SequenceExpired(seq) => write!(f, "Changes after sequence {} are no longer available", seq),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Confidence, IocContext, Severity, ThreatIntelConfig};
+    
+    fn domain(value: &str) -> Indicator {
+        Indicator {
+            id: value.to_string(),
+            ioc_type: IocType::Domain,
+            value: value.to_string(),
+            confidence: Confidence::High,
+            severity: Severity::High,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at: None,
+            sources: vec![],
+            tags: vec![],
+            context: IocContext::default(),
+            mitre_tactics: vec![],
+            mitre_techniques: vec![],
+            related_iocs: vec![],
+        }
+    }
+    
+    #[test]
+    fn test_resumable_download_verify_and_catch_up() {
+        let service = Arc::new(ThreatIntelService::new(ThreatIntelConfig::default()));
+        for i in 0..500 {
+            service.ingest(domain(&format!("bad-{}.example", i)));
+        }
+        
+        let publisher = SnapshotPublisher::new(service.clone(), SigningKey::from_bytes(&[7u8; 32]));
+        let manifest = publisher.build(IocType::Domain).unwrap();
+        assert_eq!(manifest.entries, 500);
+        
+        // Changes after the cut are served from the journal
+        service.ingest(domain("late.example"));
+        
+        // Download in small chunks, interrupted halfway
+        let get = |range: String| publisher.handle(&SnapshotRequest {
+            method: "GET".into(),
+            path: format!("/snapshots/{}", manifest.id),
+            range: Some(range),
+            ..Default::default()
+        });
+        let mut download = SnapshotDownload::new(manifest.clone());
+        while download.received() < manifest.size / 2 {
+            let response = get(download.next_range(1024).unwrap());
+            assert_eq!(response.status, 206);
+            download.append(download.received(), &response.body).unwrap();
+        }
+        
+        let mut resumed = SnapshotDownload::resume(manifest.clone(), download.partial().to_vec());
+        while let Some(range) = resumed.next_range(1024) {
+            let response = get(range);
+            resumed.append(resumed.received(), &response.body).unwrap();
+        }
+        
+        // Tampered payloads and foreign keys are rejected
+        let mut tampered = resumed.clone();
+        tampered.data[10] ^= 0xff;
+        assert!(matches!(tampered.verify(&publisher.verifying_key()), Err(SnapshotError::DigestMismatch)));
+        let other_key = SigningKey::from_bytes(&[9u8; 32]).verifying_key();
+        assert!(matches!(resumed.clone().verify(&other_key), Err(SnapshotError::UnknownKey(_))));
+        
+        let verified = resumed.verify(&publisher.verifying_key()).unwrap();
+        assert_eq!(verified.indicators.len(), 500);
+        
+        let response = publisher.handle(&SnapshotRequest {
+            method: "GET".into(),
+            path: "/changes".into(),
+            query: format!("since={}", verified.resume_after()),
+            ..Default::default()
+        });
+        let page: ChangesPage = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(page.changes.len(), 1);
+        assert_eq!(page.changes[0].indicator.value, "late.example");
+        
+        // Out-of-range requests
+        let response = get(format!("bytes={}-", manifest.size));
+        assert_eq!(response.status, 416);
+    }
+}
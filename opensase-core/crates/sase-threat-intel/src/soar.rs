@@ -0,0 +1,56 @@
+//! SOAR response backend
+//!
+//! Playbook IP blocks become confirmed indicators, so they reach every
+//! enforcement point through the regular distribution path and expire with
+//! the block.
+
+use crate::{Confidence, Indicator, IntelSource, IocContext, IocType, Reliability, Severity, ThreatIntelService};
+use sase_soc::soar::{ActionError, IpBlocklist};
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Feed ID recorded on SOAR-originated indicators
+pub const SOAR_FEED_ID: &str = "soar";
+
+#[async_trait::async_trait]
+impl IpBlocklist for ThreatIntelService {
+    async fn block_ip(&self, ip: &str, reason: &str, ttl: Option<Duration>) -> Result<String, ActionError> {
+        let addr: IpAddr = ip.parse()
+            .map_err(|_| ActionError::new(format!("Invalid IP address: {}", ip)))?;
+        let now = chrono::Utc::now();
+        let expires_at = ttl
+            .and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+            .map(|ttl| now + ttl);
+
+        let indicator = Indicator {
+            id: uuid::Uuid::new_v4().to_string(),
+            ioc_type: if addr.is_ipv4() { IocType::IPv4 } else { IocType::IPv6 },
+            value: addr.to_string(),
+            confidence: Confidence::Confirmed,
+            severity: Severity::High,
+            first_seen: now,
+            last_seen: now,
+            expires_at,
+            sources: vec![IntelSource {
+                name: "OpenSASE SOAR".to_string(),
+                feed_id: SOAR_FEED_ID.to_string(),
+                reliability: Reliability::A,
+                timestamp: now,
+                reference_url: None,
+            }],
+            tags: vec!["soar".to_string()],
+            context: IocContext {
+                description: Some(reason.to_string()),
+                ..Default::default()
+            },
+            mitre_tactics: Vec::new(),
+            mitre_techniques: Vec::new(),
+            related_iocs: Vec::new(),
+        };
+        let id = indicator.id.clone();
+
+        tracing::info!("SOAR block for {} distributed as indicator {}", addr, id);
+        self.ingest(indicator);
+        Ok(id)
+    }
+}
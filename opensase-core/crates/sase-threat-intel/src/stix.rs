@@ -271,7 +271,7 @@ fn parse_pattern(pattern: &str) -> Option<(IocType, String)> {
 }
 
 /// Map STIX label to threat type
-fn label_to_threat_type(label: &str) -> Option<ThreatType> {
+pub(crate) fn label_to_threat_type(label: &str) -> Option<ThreatType> {
     match label.to_lowercase().as_str() {
         "malware" | "malicious-activity" => Some(ThreatType::Malware),
         "botnet" => Some(ThreatType::Botnet),
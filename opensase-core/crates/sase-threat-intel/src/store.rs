@@ -0,0 +1,702 @@
+//! Indicator Persistence
+//!
+//! Two-tier IoC storage: the hot tier is the in-memory map owned by
+//! `ThreatIntelService`, the cold tier is a pluggable [`IndicatorStore`].
+//! Every indicator is written through to the cold tier so nothing is lost on
+//! restart, and the service rehydrates its hot tier and covering indexes
+//! from the store at startup.
+//!
+//! Backends:
+//! - [`SledStore`]: embedded, memory-mapped; the default for PoPs
+//! - [`PostgresStore`] (feature `postgres`): shared database for a region
+//!
+//! Wrap either in [`WriteBehindStore`] to take writes off the ingest path.
+
+use crate::{Confidence, Indicator};
+use parking_lot::{Condvar, Mutex};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Persistent cold tier for indicators
+pub trait IndicatorStore: Send + Sync {
+    /// Write an indicator
+    fn put(&self, key: &str, indicator: &Indicator) -> Result<(), StoreError>;
+
+    /// Read an indicator
+    fn get(&self, key: &str) -> Result<Option<Indicator>, StoreError>;
+
+    /// Remove an indicator
+    fn remove(&self, key: &str) -> Result<Option<Indicator>, StoreError>;
+
+    /// Number of stored indicators
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate all stored indicators, skipping undecodable records
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, Indicator)> + '_>;
+
+    /// Apply writes and removals together
+    fn write_batch(&self, puts: &[(String, Indicator)], removes: &[String]) -> Result<(), StoreError> {
+        for (key, indicator) in puts {
+            self.put(key, indicator)?;
+        }
+        for key in removes {
+            self.remove(key)?;
+        }
+        Ok(())
+    }
+
+    /// Indicators worth loading into the hot tier at startup, best first
+    fn warm_set(&self, min_confidence: Confidence, limit: usize) -> Vec<(String, Indicator)> {
+        let now = chrono::Utc::now();
+        let mut warm: Vec<_> = self.iter()
+            .filter(|(_, i)| i.confidence >= min_confidence)
+            .filter(|(_, i)| i.expires_at.map(|e| e > now).unwrap_or(true))
+            .collect();
+
+        if warm.len() > limit {
+            warm.sort_by(|(_, a), (_, b)| {
+                b.confidence.cmp(&a.confidence)
+                    .then(b.severity.cmp(&a.severity))
+                    .then(b.last_seen.cmp(&a.last_seen))
+            });
+            warm.truncate(limit);
+        }
+
+        warm
+    }
+
+    /// Delete expired indicators; returns what was removed
+    fn compact_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<(String, Indicator)>, StoreError> {
+        let expired: Vec<(String, Indicator)> = self.iter()
+            .filter(|(_, i)| i.expires_at.map(|e| e <= now).unwrap_or(false))
+            .collect();
+        let keys: Vec<String> = expired.iter().map(|(k, _)| k.clone()).collect();
+        self.write_batch(&[], &keys)?;
+
+        debug!("Compacted {} expired indicators from cold tier", expired.len());
+        Ok(expired)
+    }
+
+    /// Make buffered writes durable; returns bytes flushed where known
+    fn flush(&self) -> Result<usize, StoreError> {
+        Ok(0)
+    }
+}
+
+// =============================================================================
+// Sled
+// =============================================================================
+
+/// Sled store configuration
+#[derive(Debug, Clone)]
+pub struct StoreConfig {
+    /// On-disk location of the database
+    pub path: std::path::PathBuf,
+    /// Page cache budget in bytes
+    pub cache_capacity: u64,
+    /// Flush interval in milliseconds (None = flush only on demand)
+    pub flush_every_ms: Option<u64>,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            path: std::path::PathBuf::from("/var/lib/opensase/threat-intel/iocs"),
+            cache_capacity: 512 * 1024 * 1024,
+            flush_every_ms: Some(1000),
+        }
+    }
+}
+
+/// Embedded sled database; pages are memory-mapped and cached by the OS
+pub struct SledStore {
+    db: sled::Db,
+    indicators: sled::Tree,
+}
+
+impl SledStore {
+    /// Open (or create) the store
+    pub fn open(config: &StoreConfig) -> Result<Self, StoreError> {
+        let db = sled::Config::new()
+            .path(&config.path)
+            .cache_capacity(config.cache_capacity)
+            .flush_every_ms(config.flush_every_ms)
+            .mode(sled::Mode::HighThroughput)
+            .open()?;
+
+        let indicators = db.open_tree("indicators")?;
+
+        info!("Opened IoC store at {} ({} indicators)", config.path.display(), indicators.len());
+
+        Ok(Self { db, indicators })
+    }
+
+    /// Open a throwaway store (tests, ephemeral PoPs)
+    pub fn temporary() -> Result<Self, StoreError> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let indicators = db.open_tree("indicators")?;
+        Ok(Self { db, indicators })
+    }
+
+    /// Open a store at a path with default settings
+    pub fn open_path(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::open(&StoreConfig {
+            path: path.as_ref().to_path_buf(),
+            ..Default::default()
+        })
+    }
+
+    /// On-disk size in bytes
+    pub fn size_on_disk(&self) -> u64 {
+        self.db.size_on_disk().unwrap_or(0)
+    }
+}
+
+impl IndicatorStore for SledStore {
+    fn put(&self, key: &str, indicator: &Indicator) -> Result<(), StoreError> {
+        self.indicators.insert(key.as_bytes(), encode(indicator)?)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Indicator>, StoreError> {
+        match self.indicators.get(key.as_bytes())? {
+            Some(bytes) => decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn remove(&self, key: &str) -> Result<Option<Indicator>, StoreError> {
+        match self.indicators.remove(key.as_bytes())? {
+            Some(bytes) => decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, Indicator)> + '_> {
+        Box::new(self.indicators.iter().filter_map(|item| {
+            let (key, value) = item.ok()?;
+            let key = String::from_utf8(key.to_vec()).ok()?;
+            decode(&value).ok().map(|indicator| (key, indicator))
+        }))
+    }
+
+    fn write_batch(&self, puts: &[(String, Indicator)], removes: &[String]) -> Result<(), StoreError> {
+        let mut batch = sled::Batch::default();
+        for (key, indicator) in puts {
+            batch.insert(key.as_bytes(), encode(indicator)?);
+        }
+        for key in removes {
+            batch.remove(key.as_bytes());
+        }
+        self.indicators.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<usize, StoreError> {
+        Ok(self.db.flush()?)
+    }
+}
+
+// =============================================================================
+// PostgreSQL
+// =============================================================================
+
+#[cfg(feature = "postgres")]
+pub use self::pg::PostgresStore;
+
+#[cfg(feature = "postgres")]
+mod pg {
+    use super::*;
+
+    /// Rows fetched per page when iterating
+    const PAGE_SIZE: i64 = 10_000;
+
+    type Job = Box<dyn FnOnce(&mut postgres::Client) + Send>;
+
+    /// PostgreSQL-backed store shared by the PoPs of a region
+    ///
+    /// The synchronous client cannot run inside a tokio runtime, so it lives
+    /// on a dedicated thread and calls are handed to it. Reads on the lookup
+    /// path only happen on hot-tier misses; wrap the store in
+    /// [`WriteBehindStore`] to keep writes off the ingest path.
+    pub struct PostgresStore {
+        jobs: Mutex<std::sync::mpsc::Sender<Job>>,
+        table: String,
+    }
+
+    impl PostgresStore {
+        /// Connect and create the table if missing
+        pub fn connect(url: &str, table: &str) -> Result<Self, StoreError> {
+            if table.is_empty() || !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(StoreError::Io(format!("Invalid table name: {}", table)));
+            }
+
+            let (jobs, rx) = std::sync::mpsc::channel::<Job>();
+            let (ready_tx, ready_rx) = std::sync::mpsc::sync_channel(1);
+            let url = url.to_string();
+
+            std::thread::Builder::new()
+                .name("ioc-postgres".into())
+                .spawn(move || {
+                    let mut client = match postgres::Client::connect(&url, postgres::NoTls) {
+                        Ok(client) => {
+                            let _ = ready_tx.send(Ok(()));
+                            client
+                        }
+                        Err(e) => {
+                            let _ = ready_tx.send(Err(e.to_string()));
+                            return;
+                        }
+                    };
+
+                    for job in rx {
+                        if client.is_closed() {
+                            match postgres::Client::connect(&url, postgres::NoTls) {
+                                Ok(reconnected) => client = reconnected,
+                                Err(e) => warn!("IoC store reconnect failed: {}", e),
+                            }
+                        }
+                        job(&mut client);
+                    }
+                })
+                .map_err(|e| StoreError::Io(e.to_string()))?;
+
+            ready_rx.recv()
+                .map_err(|_| StoreError::Io("postgres worker exited".into()))?
+                .map_err(StoreError::Io)?;
+
+            let store = Self { jobs: Mutex::new(jobs), table: table.to_string() };
+            let ddl = format!(
+                "CREATE TABLE IF NOT EXISTS {t} (
+                    key TEXT PRIMARY KEY,
+                    confidence SMALLINT NOT NULL,
+                    severity SMALLINT NOT NULL,
+                    last_seen BIGINT NOT NULL,
+                    expires_at BIGINT,
+                    data BYTEA NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS {t}_expires_idx ON {t} (expires_at) WHERE expires_at IS NOT NULL;
+                CREATE INDEX IF NOT EXISTS {t}_warm_idx ON {t} (confidence DESC, severity DESC, last_seen DESC);",
+                t = store.table,
+            );
+            store.call(move |c| c.batch_execute(&ddl))?;
+
+            info!("Opened PostgreSQL IoC store {} ({} indicators)", table, store.len());
+            Ok(store)
+        }
+
+        /// Run `f` on the connection thread and wait for its result
+        fn call<R, F>(&self, f: F) -> Result<R, StoreError>
+        where
+            R: Send + 'static,
+            F: FnOnce(&mut postgres::Client) -> Result<R, postgres::Error> + Send + 'static,
+        {
+            let (tx, rx) = std::sync::mpsc::sync_channel(1);
+            self.jobs.lock()
+                .send(Box::new(move |client| {
+                    let _ = tx.send(f(client));
+                }))
+                .map_err(|_| StoreError::Io("postgres worker stopped".into()))?;
+            rx.recv()
+                .map_err(|_| StoreError::Io("postgres worker stopped".into()))?
+                .map_err(|e| StoreError::Io(e.to_string()))
+        }
+
+        fn upsert_sql(&self) -> String {
+            format!(
+                "INSERT INTO {} (key, confidence, severity, last_seen, expires_at, data)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (key) DO UPDATE SET
+                    confidence = EXCLUDED.confidence,
+                    severity = EXCLUDED.severity,
+                    last_seen = EXCLUDED.last_seen,
+                    expires_at = EXCLUDED.expires_at,
+                    data = EXCLUDED.data",
+                self.table,
+            )
+        }
+
+        /// One page of rows after `after`, in key order
+        fn page(&self, after: String) -> Result<Vec<(String, Vec<u8>)>, StoreError> {
+            let sql = format!("SELECT key, data FROM {} WHERE key > $1 ORDER BY key LIMIT $2", self.table);
+            self.call(move |c| {
+                let rows = c.query(&sql, &[&after, &PAGE_SIZE])?;
+                Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+            })
+        }
+
+        fn decode_rows(rows: Vec<(String, Vec<u8>)>) -> Vec<(String, Indicator)> {
+            rows.into_iter()
+                .filter_map(|(key, data)| decode(&data).ok().map(|i| (key, i)))
+                .collect()
+        }
+    }
+
+    /// Column values for one indicator
+    struct Row {
+        key: String,
+        confidence: i16,
+        severity: i16,
+        last_seen: i64,
+        expires_at: Option<i64>,
+        data: Vec<u8>,
+    }
+
+    impl Row {
+        fn new(key: &str, indicator: &Indicator) -> Result<Self, StoreError> {
+            Ok(Self {
+                key: key.to_string(),
+                confidence: indicator.confidence as i16,
+                severity: indicator.severity as i16,
+                last_seen: indicator.last_seen.timestamp(),
+                expires_at: indicator.expires_at.map(|e| e.timestamp()),
+                data: encode(indicator)?,
+            })
+        }
+    }
+
+    impl IndicatorStore for PostgresStore {
+        fn put(&self, key: &str, indicator: &Indicator) -> Result<(), StoreError> {
+            self.write_batch(&[(key.to_string(), indicator.clone())], &[])
+        }
+
+        fn get(&self, key: &str) -> Result<Option<Indicator>, StoreError> {
+            let sql = format!("SELECT data FROM {} WHERE key = $1", self.table);
+            let key = key.to_string();
+            let data: Option<Vec<u8>> = self.call(move |c| {
+                Ok(c.query_opt(&sql, &[&key])?.map(|r| r.get(0)))
+            })?;
+            data.map(|d| decode(&d)).transpose()
+        }
+
+        fn remove(&self, key: &str) -> Result<Option<Indicator>, StoreError> {
+            let sql = format!("DELETE FROM {} WHERE key = $1 RETURNING data", self.table);
+            let key = key.to_string();
+            let data: Option<Vec<u8>> = self.call(move |c| {
+                Ok(c.query_opt(&sql, &[&key])?.map(|r| r.get(0)))
+            })?;
+            data.map(|d| decode(&d)).transpose()
+        }
+
+        fn len(&self) -> usize {
+            let sql = format!("SELECT count(*) FROM {}", self.table);
+            self.call(move |c| c.query_one(&sql, &[]).map(|r| r.get::<_, i64>(0)))
+                .map(|n| n as usize)
+                .unwrap_or_else(|e| {
+                    warn!("IoC store count failed: {}", e);
+                    0
+                })
+        }
+
+        fn iter(&self) -> Box<dyn Iterator<Item = (String, Indicator)> + '_> {
+            // Keyset pagination so 10M rows never sit in memory at once
+            let mut after = String::new();
+            let mut done = false;
+            Box::new(std::iter::from_fn(move || {
+                if done {
+                    return None;
+                }
+                match self.page(after.clone()) {
+                    Ok(rows) => {
+                        done = (rows.len() as i64) < PAGE_SIZE;
+                        if let Some((last, _)) = rows.last() {
+                            after = last.clone();
+                        }
+                        Some(Self::decode_rows(rows))
+                    }
+                    Err(e) => {
+                        warn!("IoC store scan failed: {}", e);
+                        done = true;
+                        None
+                    }
+                }
+            }).flatten())
+        }
+
+        fn write_batch(&self, puts: &[(String, Indicator)], removes: &[String]) -> Result<(), StoreError> {
+            let rows = puts.iter()
+                .map(|(key, indicator)| Row::new(key, indicator))
+                .collect::<Result<Vec<_>, _>>()?;
+            let removes = removes.to_vec();
+            let upsert = self.upsert_sql();
+            let delete = format!("DELETE FROM {} WHERE key = ANY($1)", self.table);
+
+            self.call(move |c| {
+                let mut tx = c.transaction()?;
+                let statement = tx.prepare(&upsert)?;
+                for row in &rows {
+                    tx.execute(&statement, &[
+                        &row.key, &row.confidence, &row.severity, &row.last_seen, &row.expires_at, &row.data,
+                    ])?;
+                }
+                if !removes.is_empty() {
+                    tx.execute(&delete, &[&removes])?;
+                }
+                tx.commit()
+            })
+        }
+
+        fn warm_set(&self, min_confidence: Confidence, limit: usize) -> Vec<(String, Indicator)> {
+            let sql = format!(
+                "SELECT key, data FROM {} WHERE confidence >= $1 AND (expires_at IS NULL OR expires_at > $2)
+                 ORDER BY confidence DESC, severity DESC, last_seen DESC LIMIT $3",
+                self.table,
+            );
+            let min = min_confidence as i16;
+            let now = chrono::Utc::now().timestamp();
+            let limit = limit.min(i64::MAX as usize) as i64;
+            match self.call(move |c| {
+                let rows = c.query(&sql, &[&min, &now, &limit])?;
+                Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+            }) {
+                Ok(rows) => Self::decode_rows(rows),
+                Err(e) => {
+                    warn!("IoC store warm load failed: {}", e);
+                    Vec::new()
+                }
+            }
+        }
+
+        fn compact_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<(String, Indicator)>, StoreError> {
+            let sql = format!("DELETE FROM {} WHERE expires_at <= $1 RETURNING key, data", self.table);
+            let now = now.timestamp();
+            let rows = self.call(move |c| {
+                let rows = c.query(&sql, &[&now])?;
+                Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+            })?;
+            let expired = Self::decode_rows(rows);
+            debug!("Compacted {} expired indicators from cold tier", expired.len());
+            Ok(expired)
+        }
+    }
+}
+
+// =============================================================================
+// Write-behind
+// =============================================================================
+
+/// Write-behind buffering configuration
+#[derive(Debug, Clone)]
+pub struct WriteBehindConfig {
+    /// Maximum time a write stays buffered
+    pub flush_interval: Duration,
+    /// Buffered keys that trigger an early flush
+    pub max_pending: usize,
+}
+
+impl Default for WriteBehindConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: Duration::from_millis(500),
+            max_pending: 10_000,
+        }
+    }
+}
+
+struct WriteBehindShared {
+    inner: Box<dyn IndicatorStore>,
+    /// Latest pending state per key; `None` is a pending removal
+    pending: Mutex<HashMap<String, Option<Indicator>>>,
+    wake: Condvar,
+    stop: AtomicBool,
+    max_pending: usize,
+}
+
+impl WriteBehindShared {
+    fn flush_pending(&self) -> Result<(), StoreError> {
+        let drained: HashMap<String, Option<Indicator>> = std::mem::take(&mut *self.pending.lock());
+        if drained.is_empty() {
+            return Ok(());
+        }
+
+        let mut puts = Vec::new();
+        let mut removes = Vec::new();
+        for (key, op) in &drained {
+            match op {
+                Some(indicator) => puts.push((key.clone(), indicator.clone())),
+                None => removes.push(key.clone()),
+            }
+        }
+
+        if let Err(e) = self.inner.write_batch(&puts, &removes) {
+            // Requeue unless a newer write for the key arrived meanwhile
+            let mut pending = self.pending.lock();
+            for (key, op) in drained {
+                pending.entry(key).or_insert(op);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+/// Buffers writes and applies them to the wrapped store in batches from a
+/// background thread. Reads see buffered writes; anything buffered is lost
+/// only if the process dies before the next flush.
+pub struct WriteBehindStore {
+    shared: Arc<WriteBehindShared>,
+    worker: Option<std::thread::JoinHandle<()>>,
+}
+
+impl WriteBehindStore {
+    pub fn new(inner: impl IndicatorStore + 'static, config: WriteBehindConfig) -> Self {
+        let shared = Arc::new(WriteBehindShared {
+            inner: Box::new(inner),
+            pending: Mutex::new(HashMap::new()),
+            wake: Condvar::new(),
+            stop: AtomicBool::new(false),
+            max_pending: config.max_pending.max(1),
+        });
+
+        let worker_shared = shared.clone();
+        let worker = std::thread::Builder::new()
+            .name("ioc-write-behind".into())
+            .spawn(move || {
+                let shared = worker_shared;
+                while !shared.stop.load(Ordering::Acquire) {
+                    {
+                        let mut pending = shared.pending.lock();
+                        if pending.len() < shared.max_pending && !shared.stop.load(Ordering::Acquire) {
+                            shared.wake.wait_for(&mut pending, config.flush_interval);
+                        }
+                    }
+                    if let Err(e) = shared.flush_pending() {
+                        warn!("Write-behind flush failed, will retry: {}", e);
+                    }
+                }
+            })
+            .expect("spawn write-behind thread");
+
+        Self { shared, worker: Some(worker) }
+    }
+
+    /// Writes not yet applied to the wrapped store
+    pub fn pending(&self) -> usize {
+        self.shared.pending.lock().len()
+    }
+
+    fn enqueue(&self, key: &str, op: Option<Indicator>) {
+        let mut pending = self.shared.pending.lock();
+        pending.insert(key.to_string(), op);
+        if pending.len() >= self.shared.max_pending {
+            self.shared.wake.notify_one();
+        }
+    }
+}
+
+impl IndicatorStore for WriteBehindStore {
+    fn put(&self, key: &str, indicator: &Indicator) -> Result<(), StoreError> {
+        self.enqueue(key, Some(indicator.clone()));
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Indicator>, StoreError> {
+        if let Some(op) = self.shared.pending.lock().get(key) {
+            return Ok(op.clone());
+        }
+        self.shared.inner.get(key)
+    }
+
+    fn remove(&self, key: &str) -> Result<Option<Indicator>, StoreError> {
+        let previous = self.get(key)?;
+        self.enqueue(key, None);
+        Ok(previous)
+    }
+
+    fn len(&self) -> usize {
+        if let Err(e) = self.shared.flush_pending() {
+            warn!("Write-behind flush failed: {}", e);
+        }
+        self.shared.inner.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, Indicator)> + '_> {
+        if let Err(e) = self.shared.flush_pending() {
+            warn!("Write-behind flush failed: {}", e);
+        }
+        self.shared.inner.iter()
+    }
+
+    fn write_batch(&self, puts: &[(String, Indicator)], removes: &[String]) -> Result<(), StoreError> {
+        for (key, indicator) in puts {
+            self.enqueue(key, Some(indicator.clone()));
+        }
+        for key in removes {
+            self.enqueue(key, None);
+        }
+        Ok(())
+    }
+
+    fn warm_set(&self, min_confidence: Confidence, limit: usize) -> Vec<(String, Indicator)> {
+        if let Err(e) = self.shared.flush_pending() {
+            warn!("Write-behind flush failed: {}", e);
+        }
+        self.shared.inner.warm_set(min_confidence, limit)
+    }
+
+    fn compact_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<(String, Indicator)>, StoreError> {
+        self.shared.flush_pending()?;
+        self.shared.inner.compact_expired(now)
+    }
+
+    fn flush(&self) -> Result<usize, StoreError> {
+        self.shared.flush_pending()?;
+        self.shared.inner.flush()
+    }
+}
+
+impl Drop for WriteBehindStore {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        self.shared.wake.notify_one();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        if let Err(e) = self.flush() {
+            warn!("Final write-behind flush failed: {}", e);
+        }
+    }
+}
+
+fn encode(indicator: &Indicator) -> Result<Vec<u8>, StoreError> {
+    serde_json::to_vec(indicator).map_err(|e| StoreError::Codec(e.to_string()))
+}
+
+fn decode(bytes: &[u8]) -> Result<Indicator, StoreError> {
+    serde_json::from_slice(bytes).map_err(|e| StoreError::Codec(e.to_string()))
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(String),
+    Codec(String),
+}
+
+impl From<sled::Error> for StoreError {
+    fn from(e: sled::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Storage error: {}", e),
+            Self::Codec(e) => write!(f, "Encoding error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
@@ -0,0 +1,619 @@
+//! TAXII 2.1 Server
+//!
+//! Read-only TAXII 2.1 server publishing curated collections of
+//! our intel. Each collection is a filter (tags, confidence, type,
+//! severity) over the live indicator set, exported as STIX 2.1.
+//!
+//! The server is transport-agnostic: mount `TaxiiServer::handle` behind
+//! whatever HTTP listener fronts the platform.
+
+use crate::export::{indicator_stix_id, stix_time, StixExporter};
+use crate::{Confidence, Indicator, IocType, Severity, ThreatIntelService};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+pub const TAXII_MEDIA_TYPE: &str = "application/taxii+json;version=2.1";
+pub const STIX_MEDIA_TYPE: &str = "application/stix+json;version=2.1";
+
+/// Indicator selection for a collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollectionFilter {
+    /// Indicator must carry at least one of these tags (empty = any)
+    pub tags: Vec<String>,
+    pub min_confidence: Option<Confidence>,
+    pub min_severity: Option<Severity>,
+    /// Restrict to these IoC types (empty = any)
+    pub ioc_types: Vec<IocType>,
+}
+
+impl CollectionFilter {
+    pub fn matches(&self, indicator: &Indicator) -> bool {
+        if !self.tags.is_empty() && !indicator.tags.iter().any(|t| self.tags.contains(t)) {
+            return false;
+        }
+        if self.min_confidence.map(|c| indicator.confidence < c).unwrap_or(false) {
+            return false;
+        }
+        if self.min_severity.map(|s| indicator.severity < s).unwrap_or(false) {
+            return false;
+        }
+        self.ioc_types.is_empty() || self.ioc_types.contains(&indicator.ioc_type)
+    }
+}
+
+/// Published collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxiiCollectionDef {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub filter: CollectionFilter,
+    /// Client names allowed to read; empty means every authenticated client
+    pub readers: HashSet<String>,
+}
+
+impl TaxiiCollectionDef {
+    pub fn new(title: &str, filter: CollectionFilter) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            description: None,
+            filter,
+            readers: HashSet::new(),
+        }
+    }
+    
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+    
+    pub fn with_reader(mut self, client: &str) -> Self {
+        self.readers.insert(client.to_string());
+        self
+    }
+}
+
+/// Incoming TAXII request
+#[derive(Debug, Clone, Default)]
+pub struct TaxiiRequest {
+    pub method: String,
+    pub path: String,
+    /// Raw query string without the leading `?`
+    pub query: String,
+    /// `Authorization` header value
+    pub authorization: Option<String>,
+}
+
+/// Response to write back to the client
+#[derive(Debug, Clone)]
+pub struct TaxiiResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: Value,
+}
+
+impl TaxiiResponse {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, content_type: TAXII_MEDIA_TYPE, headers: Vec::new(), body }
+    }
+    
+    fn error(status: u16, title: &str, description: &str) -> Self {
+        Self {
+            status,
+            content_type: TAXII_MEDIA_TYPE,
+            headers: Vec::new(),
+            body: json!({ "title": title, "description": description, "http_status": status.to_string() }),
+        }
+    }
+}
+
+/// TAXII 2.1 server over the threat intel service
+pub struct TaxiiServer {
+    service: Arc<ThreatIntelService>,
+    exporter: StixExporter,
+    title: String,
+    /// API root path, e.g. `/taxii2/intel/`
+    api_root: String,
+    collections: parking_lot::RwLock<Vec<TaxiiCollectionDef>>,
+    /// Bearer token -> client name; no tokens means anonymous read
+    clients: HashMap<String, String>,
+    max_page_size: usize,
+}
+
+impl TaxiiServer {
+    pub fn new(service: Arc<ThreatIntelService>, exporter: StixExporter) -> Self {
+        Self {
+            service,
+            exporter,
+            title: "OpenSASE Threat Intelligence".to_string(),
+            api_root: "/taxii2/intel/".to_string(),
+            collections: parking_lot::RwLock::new(Vec::new()),
+            clients: HashMap::new(),
+            max_page_size: 1000,
+        }
+    }
+    
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+    
+    pub fn with_api_root(mut self, path: &str) -> Self {
+        self.api_root = format!("/{}/", path.trim_matches('/'));
+        self
+    }
+    
+    /// Require bearer authentication; `token` identifies `client`
+    pub fn with_client(mut self, token: &str, client: &str) -> Self {
+        self.clients.insert(token.to_string(), client.to_string());
+        self
+    }
+    
+    pub fn with_max_page_size(mut self, size: usize) -> Self {
+        self.max_page_size = size.max(1);
+        self
+    }
+    
+    pub fn add_collection(&self, collection: TaxiiCollectionDef) -> String {
+        let id = collection.id.clone();
+        let mut collections = self.collections.write();
+        collections.retain(|c| c.id != id);
+        collections.push(collection);
+        id
+    }
+    
+    pub fn remove_collection(&self, id: &str) -> bool {
+        let mut collections = self.collections.write();
+        let before = collections.len();
+        collections.retain(|c| c.id != id);
+        collections.len() != before
+    }
+    
+    /// Route a request
+    pub fn handle(&self, request: &TaxiiRequest) -> TaxiiResponse {
+        if !request.method.eq_ignore_ascii_case("GET") {
+            return TaxiiResponse::error(405, "Method Not Allowed", "This TAXII server is read-only");
+        }
+        
+        let client = match self.authenticate(request.authorization.as_deref()) {
+            Ok(client) => client,
+            Err(response) => return response,
+        };
+        
+        let path = format!("/{}/", request.path.trim_matches('/'));
+        if path == "/taxii2/" {
+            return self.discovery();
+        }
+        
+        let Some(rest) = path.strip_prefix(self.api_root.as_str()) else {
+            return TaxiiResponse::error(404, "Not Found", "Unknown API root");
+        };
+        let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty()).collect();
+        let query = parse_query(&request.query);
+        
+        match segments.as_slice() {
+            [] => self.api_root_info(),
+            ["collections"] => self.list_collections(client.as_deref()),
+            ["collections", id, tail @ ..] => {
+                let Some(collection) = self.readable_collection(id, client.as_deref()) else {
+                    return TaxiiResponse::error(404, "Not Found", "Unknown collection");
+                };
+                match tail {
+                    [] => TaxiiResponse::ok(collection_resource(&collection)),
+                    ["objects"] => self.objects(&collection, &query, None),
+                    ["objects", object_id] => self.objects(&collection, &query, Some(object_id)),
+                    ["objects", object_id, "versions"] => self.versions(&collection, object_id),
+                    ["manifest"] => self.manifest(&collection, &query),
+                    _ => TaxiiResponse::error(404, "Not Found", "Unknown endpoint"),
+                }
+            }
+            _ => TaxiiResponse::error(404, "Not Found", "Unknown endpoint"),
+        }
+    }
+    
+    fn authenticate(&self, authorization: Option<&str>) -> Result<Option<String>, TaxiiResponse> {
+        if self.clients.is_empty() {
+            return Ok(None);
+        }
+        
+        let token = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(str::trim);
+        match token.and_then(|t| self.clients.get(t)) {
+            Some(client) => Ok(Some(client.clone())),
+            None => {
+                let mut response = TaxiiResponse::error(401, "Unauthorized", "Valid bearer token required");
+                response.headers.push(("WWW-Authenticate".into(), "Bearer".into()));
+                Err(response)
+            }
+        }
+    }
+    
+    fn readable_collection(&self, id: &str, client: Option<&str>) -> Option<TaxiiCollectionDef> {
+        self.collections.read().iter()
+            .find(|c| c.id == id && can_read(c, client))
+            .cloned()
+    }
+    
+    fn discovery(&self) -> TaxiiResponse {
+        TaxiiResponse::ok(json!({
+            "title": self.title,
+            "default": self.api_root,
+            "api_roots": [self.api_root],
+        }))
+    }
+    
+    fn api_root_info(&self) -> TaxiiResponse {
+        TaxiiResponse::ok(json!({
+            "title": self.title,
+            "versions": [TAXII_MEDIA_TYPE],
+            "max_content_length": 10 * 1024 * 1024,
+        }))
+    }
+    
+    fn list_collections(&self, client: Option<&str>) -> TaxiiResponse {
+        let collections: Vec<Value> = self.collections.read().iter()
+            .filter(|c| can_read(c, client))
+            .map(collection_resource)
+            .collect();
+        TaxiiResponse::ok(json!({ "collections": collections }))
+    }
+    
+    /// Live indicators in the collection ordered by date added, after
+    /// applying `added_after`, `match[...]` and pagination
+    fn page(
+        &self,
+        collection: &TaxiiCollectionDef,
+        query: &HashMap<String, String>,
+        object_id: Option<&str>,
+    ) -> Result<(Vec<Indicator>, Option<String>), TaxiiResponse> {
+        let added_after = match query.get("added_after") {
+            Some(s) => Some(chrono::DateTime::parse_from_rfc3339(s)
+                .map(|d| d.with_timezone(&chrono::Utc))
+                .map_err(|_| TaxiiResponse::error(400, "Bad Request", "Invalid added_after timestamp"))?),
+            None => None,
+        };
+        let limit = query.get("limit")
+            .and_then(|l| l.parse::<usize>().ok())
+            .unwrap_or(self.max_page_size)
+            .clamp(1, self.max_page_size);
+        let cursor = match query.get("next") {
+            Some(next) => Some(parse_cursor(next)
+                .ok_or_else(|| TaxiiResponse::error(400, "Bad Request", "Invalid next cursor"))?),
+            None => None,
+        };
+        let ids: Option<HashSet<&str>> = query.get("match[id]")
+            .map(|ids| ids.split(',').collect())
+            .or_else(|| object_id.map(|id| HashSet::from([id])));
+        let wants_indicators = query.get("match[type]")
+            .map(|types| types.split(',').any(|t| t == "indicator"))
+            .unwrap_or(true);
+        // Only 2.1 content is served
+        let wants_spec = query.get("match[spec_version]")
+            .map(|versions| versions.split(',').any(|v| v == "2.1"))
+            .unwrap_or(true);
+        // We keep a single version per object, so "first", "last", "all"
+        // and its exact timestamp all select it
+        let versions: Option<HashSet<&str>> = query.get("match[version]")
+            .map(|versions| versions.split(',').collect());
+        
+        if !wants_indicators || !wants_spec {
+            return Ok((Vec::new(), None));
+        }
+        
+        let mut indicators = self.service.select(|i| {
+            collection.filter.matches(i)
+                && added_after.map(|t| i.last_seen > t).unwrap_or(true)
+                && ids.as_ref().map(|ids| ids.contains(indicator_stix_id(i).as_str())).unwrap_or(true)
+                && versions.as_ref().map(|v| matches_version(v, i)).unwrap_or(true)
+                && cursor.as_ref().map(|(at, id)| (i.last_seen, &i.id) > (*at, id)).unwrap_or(true)
+        });
+        indicators.sort_by(|a, b| a.last_seen.cmp(&b.last_seen).then_with(|| a.id.cmp(&b.id)));
+        
+        // Keyset cursor: an indicator re-sighted mid-pagination moves past
+        // the cursor and is served again rather than shifting later pages
+        let more = indicators.len() > limit;
+        indicators.truncate(limit);
+        let next = more.then(|| indicators.last().map(cursor_token)).flatten();
+        Ok((indicators, next))
+    }
+    
+    fn versions(&self, collection: &TaxiiCollectionDef, object_id: &str) -> TaxiiResponse {
+        let query = HashMap::new();
+        let (page, _) = match self.page(collection, &query, Some(object_id)) {
+            Ok(page) => page,
+            Err(response) => return response,
+        };
+        let Some(indicator) = page.first() else {
+            return TaxiiResponse::error(404, "Not Found", "Unknown object");
+        };
+        
+        let mut response = TaxiiResponse::ok(json!({
+            "more": false,
+            "versions": [object_version(indicator)],
+        }));
+        response.headers = date_added_headers(&page);
+        response
+    }
+    
+    fn objects(
+        &self,
+        collection: &TaxiiCollectionDef,
+        query: &HashMap<String, String>,
+        object_id: Option<&str>,
+    ) -> TaxiiResponse {
+        let (page, next) = match self.page(collection, query, object_id) {
+            Ok(page) => page,
+            Err(response) => return response,
+        };
+        if object_id.is_some() && page.is_empty() {
+            return TaxiiResponse::error(404, "Not Found", "Unknown object");
+        }
+        
+        let objects: Vec<Value> = page.iter()
+            .flat_map(|i| self.exporter.objects_for(i))
+            .collect();
+        
+        let mut envelope = json!({ "more": next.is_some(), "objects": objects });
+        if let Some(next) = &next {
+            envelope["next"] = json!(next);
+        }
+        
+        let mut response = TaxiiResponse::ok(envelope);
+        response.headers = date_added_headers(&page);
+        response
+    }
+    
+    fn manifest(&self, collection: &TaxiiCollectionDef, query: &HashMap<String, String>) -> TaxiiResponse {
+        let (page, next) = match self.page(collection, query, None) {
+            Ok(page) => page,
+            Err(response) => return response,
+        };
+        
+        let entries: Vec<Value> = page.iter()
+            .map(|i| json!({
+                "id": indicator_stix_id(i),
+                "date_added": stix_time(&i.last_seen),
+                "version": object_version(i),
+                "media_type": STIX_MEDIA_TYPE,
+            }))
+            .collect();
+        
+        let mut envelope = json!({ "more": next.is_some(), "objects": entries });
+        if let Some(next) = &next {
+            envelope["next"] = json!(next);
+        }
+        
+        let mut response = TaxiiResponse::ok(envelope);
+        response.headers = date_added_headers(&page);
+        response
+    }
+}
+
+fn can_read(collection: &TaxiiCollectionDef, client: Option<&str>) -> bool {
+    collection.readers.is_empty() || client.map(|c| collection.readers.contains(c)).unwrap_or(false)
+}
+
+fn collection_resource(collection: &TaxiiCollectionDef) -> Value {
+    let mut resource = json!({
+        "id": collection.id,
+        "title": collection.title,
+        "can_read": true,
+        "can_write": false,
+        "media_types": [STIX_MEDIA_TYPE],
+    });
+    if let Some(description) = &collection.description {
+        resource["description"] = json!(description);
+    }
+    resource
+}
+
+/// STIX `modified` of the exported indicator
+fn object_version(indicator: &Indicator) -> String {
+    stix_time(&indicator.last_seen.max(indicator.first_seen))
+}
+
+fn matches_version(versions: &HashSet<&str>, indicator: &Indicator) -> bool {
+    versions.iter().any(|v| matches!(*v, "first" | "last" | "all") || *v == object_version(indicator))
+}
+
+/// Opaque `next` token: date added (ns) and indicator id of the last item
+fn cursor_token(indicator: &Indicator) -> String {
+    format!("{}.{}", indicator.last_seen.timestamp_nanos_opt().unwrap_or_default(), indicator.id)
+}
+
+fn parse_cursor(token: &str) -> Option<(chrono::DateTime<chrono::Utc>, String)> {
+    let (nanos, id) = token.split_once('.')?;
+    let at = chrono::DateTime::from_timestamp_nanos(nanos.parse().ok()?);
+    Some((at, id.to_string()))
+}
+
+fn date_added_headers(page: &[Indicator]) -> Vec<(String, String)> {
+    match (page.first(), page.last()) {
+        (Some(first), Some(last)) => vec![
+            ("X-TAXII-Date-Added-First".to_string(), stix_time(&first.last_seen)),
+            ("X-TAXII-Date-Added-Last".to_string(), stix_time(&last.last_seen)),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Parse a URL query string, percent-decoding keys and values
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IocContext, ThreatIntelConfig};
+    
+    fn ioc(value: &str, confidence: Confidence, tags: &[&str]) -> Indicator {
+        Indicator {
+            id: format!("test-{}", value),
+            ioc_type: IocType::Domain,
+            value: value.to_string(),
+            confidence,
+            severity: Severity::High,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            expires_at: None,
+            sources: vec![],
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            context: IocContext { malware_family: Some("Emotet".into()), ..Default::default() },
+            mitre_tactics: vec![],
+            mitre_techniques: vec!["T1071.001".into()],
+            related_iocs: vec![],
+        }
+    }
+    
+    fn get(server: &TaxiiServer, path: &str, query: &str, token: Option<&str>) -> TaxiiResponse {
+        server.handle(&TaxiiRequest {
+            method: "GET".into(),
+            path: path.into(),
+            query: query.into(),
+            authorization: token.map(|t| format!("Bearer {}", t)),
+        })
+    }
+    
+    #[test]
+    fn test_collection_filtering_and_paging() {
+        let service = Arc::new(ThreatIntelService::new(ThreatIntelConfig::default()));
+        service.ingest(ioc("evil-1.example", Confidence::High, &["phishing"]));
+        service.ingest(ioc("evil-2.example", Confidence::Confirmed, &["phishing"]));
+        service.ingest(ioc("weak.example", Confidence::Low, &["phishing"]));
+        service.ingest(ioc("other.example", Confidence::High, &["spam"]));
+        
+        let server = TaxiiServer::new(service, StixExporter::new("OpenSASE"))
+            .with_client("partner-token", "partner")
+            .with_max_page_size(1);
+        let collection = server.add_collection(TaxiiCollectionDef::new("Phishing", CollectionFilter {
+            tags: vec!["phishing".into()],
+            min_confidence: Some(Confidence::High),
+            ..Default::default()
+        }).with_reader("partner"));
+        
+        assert_eq!(get(&server, "/taxii2/", "", None).status, 401);
+        
+        let path = format!("/taxii2/intel/collections/{}/objects/", collection);
+        let first = get(&server, &path, "", Some("partner-token"));
+        assert_eq!(first.status, 200);
+        assert_eq!(first.body["more"], true);
+        let objects = first.body["objects"].as_array().unwrap();
+        assert!(objects.iter().any(|o| o["type"] == "malware"));
+        
+        let next = first.body["next"].as_str().unwrap();
+        let second = get(&server, &path, &format!("next={}", next), Some("partner-token"));
+        assert_eq!(second.body["more"], false);
+        
+        // Exported bundles re-import through our own STIX parser
+        let bundle = serde_json::json!({
+            "type": "bundle",
+            "id": "bundle--test",
+            "objects": [first.body["objects"][0].clone(), second.body["objects"][0].clone()],
+        });
+        let config = crate::FeedConfig {
+            id: "loop".into(),
+            name: "loop".into(),
+            feed_type: crate::FeedType::StixTaxii,
+            url: String::new(),
+            api_key: None,
+            poll_interval: std::time::Duration::from_secs(60),
+            enabled: true,
+            reliability: crate::Reliability::B,
+            default_confidence: Confidence::Low,
+            ioc_types: vec![],
+            tags: vec![],
+        };
+        let parsed = crate::stix::parse_stix_bundle(&bundle.to_string(), &config).unwrap();
+        let mut values: Vec<_> = parsed.iter().map(|i| i.value.as_str()).collect();
+        values.sort();
+        assert_eq!(values, vec!["evil-1.example", "evil-2.example"]);
+        assert!(parsed.iter().all(|i| i.confidence >= Confidence::High));
+    }
+    
+    #[test]
+    fn test_cursor_survives_resighting() {
+        let service = Arc::new(ThreatIntelService::new(ThreatIntelConfig::default()));
+        let start = chrono::Utc::now();
+        for (n, value) in ["a.example", "b.example", "c.example"].iter().enumerate() {
+            let mut indicator = ioc(value, Confidence::High, &[]);
+            indicator.last_seen = start + chrono::Duration::seconds(n as i64);
+            service.ingest(indicator);
+        }
+        
+        let server = TaxiiServer::new(service.clone(), StixExporter::new("OpenSASE")).with_max_page_size(1);
+        let collection = server.add_collection(TaxiiCollectionDef::new("All", CollectionFilter::default()));
+        let path = format!("/taxii2/intel/collections/{}/manifest/", collection);
+        
+        let first = get(&server, &path, "", None);
+        assert_eq!(first.body["objects"][0]["id"], indicator_stix_id(&ioc("a.example", Confidence::High, &[])));
+        let next = first.body["next"].as_str().unwrap().to_string();
+        
+        // Re-sighting the first page's indicator must not skip "b"
+        let mut resighted = ioc("a.example", Confidence::High, &[]);
+        resighted.last_seen = start + chrono::Duration::seconds(10);
+        service.ingest(resighted);
+        
+        let mut seen = Vec::new();
+        let mut query = format!("next={}", next);
+        loop {
+            let page = get(&server, &path, &query, None);
+            assert_eq!(page.status, 200);
+            seen.push(page.body["objects"][0]["id"].as_str().unwrap().to_string());
+            match page.body["next"].as_str() {
+                Some(next) => query = format!("next={}", next),
+                None => break,
+            }
+        }
+        let expected: Vec<String> = ["b.example", "c.example", "a.example"].iter()
+            .map(|v| indicator_stix_id(&ioc(v, Confidence::High, &[])))
+            .collect();
+        assert_eq!(seen, expected);
+        
+        assert_eq!(get(&server, &path, "next=garbage", None).status, 400);
+        
+        let object_path = format!("/taxii2/intel/collections/{}/objects/{}/versions/", collection, expected[0]);
+        let versions = get(&server, &object_path, "", None);
+        assert_eq!(versions.status, 200);
+        assert_eq!(versions.body["versions"].as_array().unwrap().len(), 1);
+        
+        let objects = format!("/taxii2/intel/collections/{}/objects/", collection);
+        assert_eq!(get(&server, &objects, "match[spec_version]=2.0", None).body["objects"].as_array().unwrap().len(), 0);
+    }
+}
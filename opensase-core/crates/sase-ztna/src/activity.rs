@@ -3,6 +3,7 @@
 //! Session recording and activity logging for compliance.
 
 use crate::{Session, Resource, AccessAction};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Activity logger for session recording
@@ -84,7 +85,7 @@ pub struct DlpAlert {
     pub action_taken: DlpAction,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DlpAlertType {
     SensitiveData,
     CreditCard,
@@ -95,7 +96,7 @@ pub enum DlpAlertType {
     Credential,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DlpSeverity {
     Low,
     Medium,
@@ -103,7 +104,7 @@ pub enum DlpSeverity {
     Critical,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DlpAction {
     Logged,
     Blocked,
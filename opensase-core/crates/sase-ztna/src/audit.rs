@@ -2,7 +2,7 @@
 //!
 //! Comprehensive audit trail for zero trust access.
 
-use crate::{AccessRequest, AccessDecision, Decision};
+use crate::{AccessAction, AccessRequest, AccessDecision, Decision, Session};
 use std::time::Duration;
 
 /// Audit logger
@@ -42,10 +42,29 @@ pub enum AuditEventType {
     SessionTerminated,
     SessionSuspended,
     SessionExpired,
+    /// Continuous evaluation lowered a session's trust level
+    TrustDowngraded,
+    /// Continuous evaluation required step-up at the next request
+    StepUpRequired,
     PolicyViolation,
     RiskSignal,
     DeviceRegistered,
     DeviceBlocked,
+    ActivityReportGenerated,
+    ActivityReportDenied,
+    JitRequested,
+    JitApproved,
+    JitDenied,
+    JitCancelled,
+    JitGranted,
+    JitSessionBound,
+    JitTimedOut,
+    JitExpired,
+    JitRevoked,
+    DeviceCertificateIssued,
+    DeviceCertificateRenewed,
+    DeviceCertificateRevoked,
+    DeviceEnrollmentDenied,
 }
 
 impl AuditLogger {
@@ -179,6 +198,187 @@ impl AuditLogger {
         self.store_event(event);
     }
     
+    /// Log a federated login through a tenant's IdP. `user_id` is None
+    /// when the login failed before the user was known.
+    pub async fn log_federated_login(
+        &self,
+        tenant_id: &str,
+        provider_id: &str,
+        user_id: Option<&str>,
+        error: Option<&str>,
+    ) {
+        let mut details = std::collections::HashMap::new();
+        details.insert("tenant_id".to_string(), tenant_id.to_string());
+        details.insert("provider_id".to_string(), provider_id.to_string());
+        if let Some(error) = error {
+            details.insert("error".to_string(), error.to_string());
+        }
+        let event = AuditEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type: if error.is_none() {
+                AuditEventType::AuthenticationSuccess
+            } else {
+                AuditEventType::AuthenticationFailure
+            },
+            timestamp: chrono::Utc::now(),
+            user_id: user_id.map(String::from),
+            session_id: None,
+            resource_id: None,
+            action: Some("sso".to_string()),
+            decision: None,
+            details,
+            client_ip: None,
+            processing_time_ms: None,
+        };
+        
+        self.store_event(event);
+    }
+    
+    /// Log session creation
+    pub async fn log_session_created(&self, session: &Session, method: &str) {
+        let event = AuditEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type: AuditEventType::SessionCreated,
+            timestamp: chrono::Utc::now(),
+            user_id: Some(session.identity.user_id.clone()),
+            session_id: Some(session.id.clone()),
+            resource_id: None,
+            action: Some(method.to_string()),
+            decision: None,
+            details: std::collections::HashMap::new(),
+            client_ip: None,
+            processing_time_ms: None,
+        };
+        
+        self.store_event(event);
+    }
+    
+    /// Log a continuous-evaluation outcome for a session: a new risk
+    /// signal, a trust downgrade, a step-up, a suspension or a revocation
+    pub async fn log_session_risk(
+        &self,
+        event_type: AuditEventType,
+        session: &Session,
+        risk_score: f64,
+        mut details: std::collections::HashMap<String, String>,
+    ) {
+        let decision = match event_type {
+            AuditEventType::StepUpRequired => Some(Decision::StepUp),
+            AuditEventType::SessionSuspended | AuditEventType::SessionTerminated => Some(Decision::Deny),
+            _ => None,
+        };
+        details.insert("risk_score".to_string(), format!("{:.1}", risk_score));
+        let event = AuditEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            timestamp: chrono::Utc::now(),
+            user_id: Some(session.identity.user_id.clone()),
+            session_id: Some(session.id.clone()),
+            resource_id: None,
+            action: Some("continuous_evaluation".to_string()),
+            decision,
+            details,
+            client_ip: None,
+            processing_time_ms: None,
+        };
+        
+        self.store_event(event);
+    }
+    
+    /// Log an investigator's request for a user activity report
+    pub async fn log_activity_report(
+        &self,
+        investigator_id: &str,
+        subject_user_id: &str,
+        granted: bool,
+        details: std::collections::HashMap<String, String>,
+    ) {
+        let event = AuditEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type: if granted {
+                AuditEventType::ActivityReportGenerated
+            } else {
+                AuditEventType::ActivityReportDenied
+            },
+            timestamp: chrono::Utc::now(),
+            user_id: Some(investigator_id.to_string()),
+            session_id: None,
+            resource_id: Some(format!("user:{}", subject_user_id)),
+            action: Some("activity_report".to_string()),
+            decision: Some(if granted { Decision::Allow } else { Decision::Deny }),
+            details,
+            client_ip: None,
+            processing_time_ms: None,
+        };
+        
+        self.store_event(event);
+    }
+    
+    /// Log a step of a just-in-time access request. `actor_id` is whoever
+    /// acted (requester, approver or `system`); the requester is in the details.
+    pub async fn log_jit(
+        &self,
+        event_type: AuditEventType,
+        actor_id: &str,
+        resource_id: &str,
+        action: AccessAction,
+        session_id: Option<&str>,
+        details: std::collections::HashMap<String, String>,
+    ) {
+        let decision = match event_type {
+            AuditEventType::JitGranted | AuditEventType::JitApproved => Some(Decision::Allow),
+            AuditEventType::JitDenied => Some(Decision::Deny),
+            AuditEventType::JitRequested => Some(Decision::Review),
+            _ => None,
+        };
+        let event = AuditEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            timestamp: chrono::Utc::now(),
+            user_id: Some(actor_id.to_string()),
+            session_id: session_id.map(str::to_string),
+            resource_id: Some(resource_id.to_string()),
+            action: Some(format!("{:?}", action)),
+            decision,
+            details,
+            client_ip: None,
+            processing_time_ms: None,
+        };
+        
+        self.store_event(event);
+    }
+    
+    /// Log device CA activity. `actor_id` is the device for enrollment and
+    /// whoever revoked (an admin or `system`) for revocation.
+    pub async fn log_device_certificate(
+        &self,
+        event_type: AuditEventType,
+        actor_id: &str,
+        device_id: &str,
+        details: std::collections::HashMap<String, String>,
+    ) {
+        let decision = match event_type {
+            AuditEventType::DeviceCertificateIssued | AuditEventType::DeviceCertificateRenewed => Some(Decision::Allow),
+            AuditEventType::DeviceEnrollmentDenied => Some(Decision::Deny),
+            _ => None,
+        };
+        let event = AuditEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            timestamp: chrono::Utc::now(),
+            user_id: Some(actor_id.to_string()),
+            session_id: None,
+            resource_id: Some(device_id.to_string()),
+            action: None,
+            decision,
+            details,
+            client_ip: None,
+            processing_time_ms: None,
+        };
+        
+        self.store_event(event);
+    }
+    
     fn store_event(&self, event: AuditEvent) {
         tracing::info!(
             event_type = ?event.event_type,
@@ -201,6 +401,20 @@ impl AuditLogger {
                     }
                 }
                 
+                // Filter by session
+                if let Some(session_id) = &query.session_id {
+                    if e.session_id.as_ref() != Some(session_id) {
+                        return false;
+                    }
+                }
+                
+                // Filter by resource
+                if let Some(resource_id) = &query.resource_id {
+                    if e.resource_id.as_ref() != Some(resource_id) {
+                        return false;
+                    }
+                }
+                
                 // Filter by event type
                 if !query.event_types.is_empty() {
                     if !query.event_types.contains(&e.event_type) {
@@ -1,304 +0,0 @@
-//! Clientless ZTNA Gateway
-//!
-//! Browser-based access to applications without client software.
-
-use crate::{Session, Resource};
-use std::collections::HashMap;
-
-/// Clientless ZTNA gateway for browser-based access
-pub struct ClientlessGateway {
-    app_proxy: AppProxy,
-    session_recorder: SessionRecorder,
-}
-
-struct AppProxy;
-struct SessionRecorder;
-
-#[derive(Clone)]
-pub struct ConnectedApp {
-    pub id: String,
-    pub name: String,
-    pub app_type: AppType,
-    pub internal_host: String,
-    pub internal_port: u16,
-    pub protocol: AppProtocol,
-    pub access_policy: AppAccessPolicy,
-}
-
-#[derive(Clone)]
-pub enum AppType {
-    Web { path_prefix: String },
-    Ssh,
-    Rdp,
-    Database { db_type: String },
-    Vnc,
-    Custom { protocol: String },
-}
-
-#[derive(Clone, Copy)]
-pub enum AppProtocol {
-    Http,
-    Https,
-    Ssh,
-    Rdp,
-    Tcp,
-    Udp,
-}
-
-#[derive(Clone)]
-pub struct AppAccessPolicy {
-    pub min_trust_score: f64,
-    pub require_mfa: bool,
-    pub record_session: bool,
-    pub dlp_enabled: bool,
-    pub allowed_actions: Vec<AllowedAction>,
-}
-
-#[derive(Clone, Copy)]
-pub enum AllowedAction {
-    Read,
-    Write,
-    Execute,
-    Upload,
-    Download,
-    Clipboard,
-    Print,
-}
-
-#[derive(Clone)]
-pub struct HttpRequest {
-    pub method: String,
-    pub path: String,
-    pub headers: HashMap<String, String>,
-    pub body: Vec<u8>,
-}
-
-#[derive(Clone)]
-pub struct HttpResponse {
-    pub status: u16,
-    pub headers: HashMap<String, String>,
-    pub body: Vec<u8>,
-}
-
-impl HttpResponse {
-    pub fn forbidden() -> Self {
-        Self {
-            status: 403,
-            headers: HashMap::new(),
-            body: b"Forbidden".to_vec(),
-        }
-    }
-}
-
-impl ClientlessGateway {
-    pub fn new() -> Self {
-        Self {
-            app_proxy: AppProxy,
-            session_recorder: SessionRecorder,
-        }
-    }
-    
-    /// Handle browser-based web application access
-    pub async fn handle_web_access(
-        &self,
-        session: &Session,
-        app: &ConnectedApp,
-        request: HttpRequest,
-    ) -> Result<HttpResponse, ClientlessError> {
-        // Verify session trust
-        if session.risk_score > 50.0 {
-            return Err(ClientlessError::InsufficientTrust);
-        }
-        
-        // Rewrite request for internal app
-        let internal_request = self.rewrite_request(&request, app)?;
-        
-        // Proxy to internal application
-        let internal_response = self.proxy_request(app, internal_request).await?;
-        
-        // Rewrite response URLs
-        let response = self.rewrite_response(internal_response, app)?;
-        
-        // DLP scanning
-        if app.access_policy.dlp_enabled {
-            self.scan_response(&session.id, &response).await?;
-        }
-        
-        // Log access
-        self.log_access(session, app, &request).await;
-        
-        Ok(response)
-    }
-    
-    fn rewrite_request(&self, request: &HttpRequest, app: &ConnectedApp) -> Result<HttpRequest, ClientlessError> {
-        let mut rewritten = request.clone();
-        
-        // Update Host header
-        rewritten.headers.insert(
-            "Host".to_string(),
-            format!("{}:{}", app.internal_host, app.internal_port),
-        );
-        
-        // Remove/modify headers that shouldn't go to backend
-        rewritten.headers.remove("X-Forwarded-For");
-        rewritten.headers.remove("X-Real-IP");
-        
-        Ok(rewritten)
-    }
-    
-    async fn proxy_request(&self, app: &ConnectedApp, request: HttpRequest) -> Result<HttpResponse, ClientlessError> {
-        // In production: actual HTTP proxy
-        tracing::debug!(
-            "Proxying {} {} to {}:{}",
-            request.method, request.path,
-            app.internal_host, app.internal_port
-        );
-        
-        Ok(HttpResponse {
-            status: 200,
-            headers: HashMap::new(),
-            body: Vec::new(),
-        })
-    }
-    
-    fn rewrite_response(&self, response: HttpResponse, _app: &ConnectedApp) -> Result<HttpResponse, ClientlessError> {
-        // Rewrite URLs in response to go through gateway
-        // In production: parse HTML/CSS/JS and rewrite links
-        Ok(response)
-    }
-    
-    async fn scan_response(&self, session_id: &str, response: &HttpResponse) -> Result<(), ClientlessError> {
-        // DLP scanning of response content
-        let content = String::from_utf8_lossy(&response.body);
-        
-        // Check for sensitive patterns
-        if self.contains_sensitive_data(&content) {
-            tracing::warn!("DLP: Sensitive data detected in session {}", session_id);
-            // Could block or redact
-        }
-        
-        Ok(())
-    }
-    
-    fn contains_sensitive_data(&self, content: &str) -> bool {
-        // Simple pattern checks
-        let patterns = [
-            r"\d{4}[- ]?\d{4}[- ]?\d{4}[- ]?\d{4}", // Credit card
-            r"\d{3}-\d{2}-\d{4}", // SSN
-        ];
-        
-        for pattern in patterns {
-            if regex::Regex::new(pattern).ok().map(|r| r.is_match(content)).unwrap_or(false) {
-                return true;
-            }
-        }
-        
-        false
-    }
-    
-    async fn log_access(&self, session: &Session, app: &ConnectedApp, request: &HttpRequest) {
-        tracing::info!(
-            "Clientless access: session={} app={} path={}",
-            session.id, app.name, request.path
-        );
-    }
-    
-    /// Handle SSH via browser (terminal emulation)
-    pub async fn handle_ssh_access(
-        &self,
-        session: &Session,
-        app: &ConnectedApp,
-    ) -> Result<SshSession, ClientlessError> {
-        if !matches!(app.app_type, AppType::Ssh) {
-            return Err(ClientlessError::ProtocolMismatch);
-        }
-        
-        tracing::info!(
-            "Starting SSH session for user {} to {}",
-            session.identity.user_id, app.internal_host
-        );
-        
-        Ok(SshSession {
-            id: uuid::Uuid::new_v4().to_string(),
-            session_id: session.id.clone(),
-            app_id: app.id.clone(),
-            terminal_cols: 80,
-            terminal_rows: 24,
-            recording_enabled: app.access_policy.record_session,
-        })
-    }
-    
-    /// Handle RDP via browser
-    pub async fn handle_rdp_access(
-        &self,
-        session: &Session,
-        app: &ConnectedApp,
-    ) -> Result<RdpSession, ClientlessError> {
-        if !matches!(app.app_type, AppType::Rdp) {
-            return Err(ClientlessError::ProtocolMismatch);
-        }
-        
-        tracing::info!(
-            "Starting RDP session for user {} to {}",
-            session.identity.user_id, app.internal_host
-        );
-        
-        Ok(RdpSession {
-            id: uuid::Uuid::new_v4().to_string(),
-            session_id: session.id.clone(),
-            app_id: app.id.clone(),
-            width: 1920,
-            height: 1080,
-            recording_enabled: app.access_policy.record_session,
-        })
-    }
-}
-
-impl Default for ClientlessGateway {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-#[derive(Clone)]
-pub struct SshSession {
-    pub id: String,
-    pub session_id: String,
-    pub app_id: String,
-    pub terminal_cols: u16,
-    pub terminal_rows: u16,
-    pub recording_enabled: bool,
-}
-
-#[derive(Clone)]
-pub struct RdpSession {
-    pub id: String,
-    pub session_id: String,
-    pub app_id: String,
-    pub width: u32,
-    pub height: u32,
-    pub recording_enabled: bool,
-}
-
-#[derive(Debug)]
-pub enum ClientlessError {
-    Unauthorized,
-    InsufficientTrust,
-    ProtocolMismatch,
-    ProxyError,
-    DlpBlocked,
-}
-
-impl std::fmt::Display for ClientlessError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Unauthorized => write!(f, "Unauthorized"),
-            Self::InsufficientTrust => write!(f, "Insufficient trust score"),
-            Self::ProtocolMismatch => write!(f, "Protocol mismatch"),
-            Self::ProxyError => write!(f, "Proxy error"),
-            Self::DlpBlocked => write!(f, "Blocked by DLP policy"),
-        }
-    }
-}
-
-impl std::error::Error for ClientlessError {}
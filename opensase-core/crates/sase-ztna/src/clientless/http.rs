@@ -0,0 +1,403 @@
+//! HTTP(S) Reverse Proxy
+//!
+//! Publishes an internal web application under a portal path prefix. The
+//! proxy strips anything the browser could use to impersonate the portal,
+//! injects the user's identity as headers the application trusts (optionally
+//! with an HMAC-signed assertion), and rewrites redirects, cookies and
+//! root-relative links in HTML so the app keeps working under the prefix.
+
+use super::{AppProtocol, AppType, ClientlessError, ClientlessGrant, ConnectedApp, HttpRequest, HttpResponse, RecordingSink};
+use crate::recording::{FileOperation, NetworkPacketData, PacketDirection, RecordedActivity};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Portal's own session cookie; never forwarded to applications
+pub const PORTAL_COOKIE: &str = "opensase_portal";
+
+/// Largest upstream body the proxy will buffer
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Headers describing the client that only the portal may set
+const FORWARDING: &[&str] = &[
+    "x-forwarded-for",
+    "x-forwarded-host",
+    "x-forwarded-proto",
+    "x-forwarded-prefix",
+    "x-real-ip",
+    "forwarded",
+];
+
+/// Identity headers added to every proxied request
+#[derive(Debug, Clone)]
+pub struct SsoInjection {
+    /// Header name → template. Placeholders: `{user_id}`, `{email}`,
+    /// `{name}`, `{groups}`, `{roles}`, `{session_id}`
+    pub headers: Vec<(String, String)>,
+    /// Key for a signed assertion the application can verify
+    pub signing_key: Option<Vec<u8>>,
+    pub assertion_header: String,
+    /// Lifetime of the signed assertion
+    pub assertion_ttl_secs: i64,
+}
+
+impl Default for SsoInjection {
+    fn default() -> Self {
+        Self {
+            headers: vec![
+                ("X-Remote-User".to_string(), "{user_id}".to_string()),
+                ("X-Remote-Email".to_string(), "{email}".to_string()),
+                ("X-Remote-Groups".to_string(), "{groups}".to_string()),
+            ],
+            signing_key: None,
+            assertion_header: "X-OpenSASE-Assertion".to_string(),
+            assertion_ttl_secs: 60,
+        }
+    }
+}
+
+impl SsoInjection {
+    pub fn with_header(mut self, name: &str, template: &str) -> Self {
+        self.headers.push((name.to_string(), template.to_string()));
+        self
+    }
+
+    pub fn with_signing_key(mut self, key: &[u8]) -> Self {
+        self.signing_key = Some(key.to_vec());
+        self
+    }
+
+    fn render(template: &str, grant: &ClientlessGrant) -> String {
+        let identity = &grant.identity;
+        template
+            .replace("{user_id}", &identity.user_id)
+            .replace("{email}", &identity.email)
+            .replace("{name}", &identity.name)
+            .replace("{groups}", &identity.groups.join(","))
+            .replace("{roles}", &identity.roles.join(","))
+            .replace("{session_id}", &grant.session_id)
+    }
+
+    /// `base64url(claims).base64url(HMAC-SHA256)`
+    fn assertion(&self, key: &[u8], grant: &ClientlessGrant) -> Option<String> {
+        #[derive(Serialize)]
+        struct Claims<'a> {
+            sub: &'a str,
+            email: &'a str,
+            groups: &'a [String],
+            app: &'a str,
+            sid: &'a str,
+            iat: i64,
+            exp: i64,
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = Claims {
+            sub: &grant.identity.user_id,
+            email: &grant.identity.email,
+            groups: &grant.identity.groups,
+            app: &grant.app_id,
+            sid: &grant.session_id,
+            iat: now,
+            exp: now + self.assertion_ttl_secs,
+        };
+
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let payload = engine.encode(serde_json::to_vec(&claims).ok()?);
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).ok()?;
+        mac.update(payload.as_bytes());
+        let signature = engine.encode(mac.finalize().into_bytes());
+        Some(format!("{}.{}", payload, signature))
+    }
+
+    fn apply(&self, headers: &mut Vec<(String, String)>, grant: &ClientlessGrant) {
+        // Browser-supplied copies would let a user claim any identity
+        headers.retain(|(name, _)| {
+            !self.headers.iter().any(|(injected, _)| injected.eq_ignore_ascii_case(name))
+                && !self.assertion_header.eq_ignore_ascii_case(name)
+        });
+
+        for (name, template) in &self.headers {
+            headers.push((name.clone(), Self::render(template, grant)));
+        }
+        if let Some(key) = &self.signing_key {
+            if let Some(assertion) = self.assertion(key, grant) {
+                headers.push((self.assertion_header.clone(), assertion));
+            }
+        }
+    }
+}
+
+/// Reverse proxy for web applications
+pub struct HttpGateway {
+    client: reqwest::Client,
+}
+
+impl HttpGateway {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            // Redirects go back to the browser, rewritten
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(60))
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+
+    pub async fn proxy(
+        &self,
+        grant: &ClientlessGrant,
+        app: &ConnectedApp,
+        request: &HttpRequest,
+    ) -> Result<HttpResponse, ClientlessError> {
+        let prefix = public_prefix(app);
+        let origin = internal_origin(app);
+        let path = strip_prefix(&request.path, &prefix);
+
+        let headers = self.upstream_headers(grant, app, request, &prefix);
+        let method = reqwest::Method::from_bytes(request.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| ClientlessError::Protocol(format!("invalid method {}", request.method)))?;
+
+        let mut upstream = self.client.request(method, format!("{}{}", origin, path));
+        for (name, value) in &headers {
+            upstream = upstream.header(name.as_str(), value.as_str());
+        }
+        if !request.body.is_empty() {
+            upstream = upstream.body(request.body.clone());
+        }
+
+        let response = upstream.send().await.map_err(|e| {
+            tracing::warn!("Clientless proxy to {} failed: {}", app.name, e);
+            ClientlessError::Upstream(e.to_string())
+        })?;
+
+        let status = response.status().as_u16();
+        let mut response_headers = HashMap::new();
+        let mut set_cookies = Vec::new();
+        for (name, value) in response.headers() {
+            let Ok(value) = value.to_str() else { continue };
+            let name = name.as_str();
+            if HOP_BY_HOP.contains(&name) || name == "content-length" {
+                continue;
+            }
+            match name {
+                "set-cookie" => set_cookies.push(rewrite_cookie(value, &prefix)),
+                "location" | "content-location" => {
+                    response_headers.insert(name.to_string(), rewrite_location(value, &origin, &prefix));
+                }
+                _ => {
+                    response_headers.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+
+        if response.content_length().map(|len| len as usize > MAX_BODY).unwrap_or(false) {
+            return Err(ClientlessError::Upstream("response too large".to_string()));
+        }
+        let mut body = response.bytes().await
+            .map_err(|e| ClientlessError::Upstream(e.to_string()))?
+            .to_vec();
+
+        let is_html = response_headers.get("content-type")
+            .map(|ct| ct.starts_with("text/html"))
+            .unwrap_or(false);
+        if is_html && !prefix.is_empty() {
+            body = rewrite_html(&String::from_utf8_lossy(&body), &origin, &prefix).into_bytes();
+        }
+        response_headers.insert("content-length".to_string(), body.len().to_string());
+
+        Ok(HttpResponse { status, headers: response_headers, set_cookies, body })
+    }
+
+    fn upstream_headers(
+        &self,
+        grant: &ClientlessGrant,
+        app: &ConnectedApp,
+        request: &HttpRequest,
+        prefix: &str,
+    ) -> Vec<(String, String)> {
+        let mut headers: Vec<(String, String)> = request.headers.iter()
+            .filter(|(name, _)| {
+                let lower = name.to_ascii_lowercase();
+                !HOP_BY_HOP.contains(&lower.as_str())
+                    && !FORWARDING.contains(&lower.as_str())
+                    && lower != "host"
+                    && lower != "content-length"
+                    // Rewriting needs identity bodies
+                    && lower != "accept-encoding"
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        // Drop the portal's own cookie
+        if let Some(index) = headers.iter().position(|(name, _)| name.eq_ignore_ascii_case("cookie")) {
+            let cookies = strip_portal_cookie(&headers[index].1);
+            if cookies.is_empty() {
+                headers.remove(index);
+            } else {
+                headers[index].1 = cookies;
+            }
+        }
+
+        headers.push(("Accept-Encoding".to_string(), "identity".to_string()));
+        if !prefix.is_empty() {
+            headers.push(("X-Forwarded-Prefix".to_string(), prefix.to_string()));
+        }
+        if let Some(sso) = &app.sso {
+            sso.apply(&mut headers, grant);
+        }
+        headers
+    }
+}
+
+impl Default for HttpGateway {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn public_prefix(app: &ConnectedApp) -> String {
+    match &app.app_type {
+        AppType::Web { path_prefix } => path_prefix.trim_end_matches('/').to_string(),
+        _ => String::new(),
+    }
+}
+
+fn internal_origin(app: &ConnectedApp) -> String {
+    let scheme = match app.protocol {
+        AppProtocol::Https => "https",
+        _ => "http",
+    };
+    format!("{}://{}:{}", scheme, app.internal_host, app.internal_port)
+}
+
+fn strip_prefix(path: &str, prefix: &str) -> String {
+    let stripped = path.strip_prefix(prefix).unwrap_or(path);
+    if stripped.starts_with('/') {
+        stripped.to_string()
+    } else {
+        format!("/{}", stripped)
+    }
+}
+
+fn strip_portal_cookie(header: &str) -> String {
+    header.split(';')
+        .map(str::trim)
+        .filter(|c| !c.is_empty() && !c.starts_with(&format!("{}=", PORTAL_COOKIE)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Map internal redirects onto the portal prefix
+fn rewrite_location(location: &str, origin: &str, prefix: &str) -> String {
+    if let Some(rest) = location.strip_prefix(origin) {
+        format!("{}{}", prefix, if rest.is_empty() { "/" } else { rest })
+    } else if location.starts_with('/') && !location.starts_with("//") {
+        format!("{}{}", prefix, location)
+    } else {
+        location.to_string()
+    }
+}
+
+/// Scope cookies to the prefix; drop `Domain` so they stay on the portal
+fn rewrite_cookie(cookie: &str, prefix: &str) -> String {
+    let mut has_path = false;
+    let mut parts: Vec<String> = cookie.split(';')
+        .map(str::trim)
+        .filter(|attr| !attr.to_ascii_lowercase().starts_with("domain="))
+        .map(|attr| {
+            if attr.to_ascii_lowercase().starts_with("path=") {
+                has_path = true;
+                format!("Path={}{}", prefix, &attr[5..])
+            } else {
+                attr.to_string()
+            }
+        })
+        .collect();
+    if !has_path && !prefix.is_empty() {
+        parts.push(format!("Path={}/", prefix));
+    }
+    parts.join("; ")
+}
+
+/// Point root-relative and absolute internal links at the prefix
+fn rewrite_html(html: &str, origin: &str, prefix: &str) -> String {
+    let absolute = html.replace(&format!("{}/", origin), "/");
+    let pattern = regex::Regex::new(r#"(?i)\b(href|src|action|formaction)=(["'])/([^/])"#)
+        .expect("static regex");
+    pattern.replace_all(&absolute, |caps: &regex::Captures| {
+        format!("{}={}{}/{}", &caps[1], &caps[2], prefix, &caps[3])
+    }).into_owned()
+}
+
+pub(crate) fn is_upload(request: &HttpRequest) -> bool {
+    request.headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("content-type") && value.starts_with("multipart/form-data")
+    })
+}
+
+pub(crate) fn is_download(response: &HttpResponse) -> bool {
+    response.headers.get("content-disposition")
+        .map(|cd| cd.trim_start().to_ascii_lowercase().starts_with("attachment"))
+        .unwrap_or(false)
+}
+
+fn attachment_name(response: &HttpResponse) -> Option<String> {
+    let disposition = response.headers.get("content-disposition")?;
+    let start = disposition.find("filename=")? + "filename=".len();
+    Some(disposition[start..].split(';').next()?.trim().trim_matches('"').to_string())
+}
+
+/// Record one request/response pair, plus any file transfer it carried
+pub(crate) async fn record_exchange(
+    sink: &RecordingSink,
+    grant: &ClientlessGrant,
+    request: &HttpRequest,
+    response: &HttpResponse,
+) {
+    let now = chrono::Utc::now();
+    let target = format!("{} {} -> {}", request.method, request.path, response.status);
+
+    sink.recorder.record(&sink.recording_id, RecordedActivity::NetworkPacket(NetworkPacketData {
+        timestamp: now,
+        direction: PacketDirection::Outbound,
+        protocol: "HTTP".to_string(),
+        src: grant.identity.user_id.clone(),
+        dst: target.clone(),
+        size: request.body.len() as u32,
+    })).await;
+    sink.recorder.record(&sink.recording_id, RecordedActivity::NetworkPacket(NetworkPacketData {
+        timestamp: now,
+        direction: PacketDirection::Inbound,
+        protocol: "HTTP".to_string(),
+        src: target,
+        dst: grant.identity.user_id.clone(),
+        size: response.body.len() as u32,
+    })).await;
+
+    if is_upload(request) {
+        sink.recorder
+            .record_file_access(&sink.recording_id, FileOperation::Upload, &request.path, request.body.len() as u64)
+            .await;
+    }
+    if is_download(response) {
+        let name = attachment_name(response).unwrap_or_else(|| request.path.clone());
+        sink.recorder
+            .record_file_access(&sink.recording_id, FileOperation::Download, &name, response.body.len() as u64)
+            .await;
+    }
+}
@@ -0,0 +1,562 @@
+//! Clientless ZTNA Gateway
+//!
+//! Browser-based access to applications without client software.
+//!
+//! Every portal session starts with a `request_access` decision for the
+//! application; only an `Allow` yields a [`ClientlessGrant`], and every
+//! later request or stream is checked against that grant and the live
+//! ZTNA session. Sessions are recorded when the application policy asks
+//! for it, and refused if no recorder is available.
+//!
+//! - [`portal`]: browser entry point routing published apps, bound to the
+//!   ZTNA session by cookie
+//! - [`http`]: HTTP(S) reverse proxy with header-based SSO injection
+//! - [`ssh`]: SSH terminal bridge for a browser terminal
+//! - [`rdp`]: RDP through guacd, translating the Guacamole protocol
+
+pub mod http;
+pub mod portal;
+pub mod rdp;
+pub mod ssh;
+
+pub use http::{HttpGateway, SsoInjection};
+pub use portal::{ClientlessPortal, PortalClient, PortalConfig, PublishedApp};
+pub use rdp::{GuacInstruction, GuacamoleSession, RdpGateway};
+pub use ssh::{SshGateway, TerminalInput, TerminalOutput, TerminalSession};
+
+use crate::recording::{EnhancedSessionRecorder, RecordingType};
+use crate::stepup::StepUpGate;
+use crate::{
+    AccessCondition, AccessRequest, Decision, Identity, SessionStatus, ZeroTrustGateway,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Clientless ZTNA gateway for browser-based access
+pub struct ClientlessGateway {
+    access: Arc<ZeroTrustGateway>,
+    recorder: Option<Arc<EnhancedSessionRecorder>>,
+    http: HttpGateway,
+    ssh: SshGateway,
+    rdp: RdpGateway,
+    grants: dashmap::DashMap<String, ClientlessGrant>,
+}
+
+#[derive(Clone)]
+pub struct ConnectedApp {
+    pub id: String,
+    pub name: String,
+    pub app_type: AppType,
+    pub internal_host: String,
+    pub internal_port: u16,
+    pub protocol: AppProtocol,
+    pub access_policy: AppAccessPolicy,
+    /// Gateway-held login for SSH/RDP; never sent to the browser
+    pub credential: Option<AppCredential>,
+    /// Identity headers injected into proxied web requests
+    pub sso: Option<SsoInjection>,
+    /// Pinned SSH host key (SHA-256 fingerprint)
+    pub host_key_fingerprint: Option<String>,
+}
+
+#[derive(Clone)]
+pub enum AppType {
+    /// `path_prefix` is where the portal publishes the app, e.g. `/apps/wiki`
+    Web { path_prefix: String },
+    Ssh,
+    Rdp,
+    Database { db_type: String },
+    Vnc,
+    Custom { protocol: String },
+}
+
+#[derive(Clone, Copy)]
+pub enum AppProtocol {
+    Http,
+    Https,
+    Ssh,
+    Rdp,
+    Tcp,
+    Udp,
+}
+
+#[derive(Clone)]
+pub struct AppAccessPolicy {
+    pub min_trust_score: f64,
+    pub require_mfa: bool,
+    pub record_session: bool,
+    pub dlp_enabled: bool,
+    pub allowed_actions: Vec<AllowedAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowedAction {
+    Read,
+    Write,
+    Execute,
+    Upload,
+    Download,
+    Clipboard,
+    Print,
+}
+
+#[derive(Clone)]
+pub struct AppCredential {
+    pub username: String,
+    pub secret: CredentialSecret,
+    /// Windows domain for RDP
+    pub domain: Option<String>,
+}
+
+#[derive(Clone)]
+pub enum CredentialSecret {
+    Password(String),
+    /// OpenSSH or PEM private key
+    PrivateKey { pem: String, passphrase: Option<String> },
+}
+
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    /// `Set-Cookie` values, kept apart since there may be several
+    pub set_cookies: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn forbidden() -> Self {
+        Self {
+            status: 403,
+            headers: HashMap::new(),
+            set_cookies: Vec::new(),
+            body: b"Forbidden".to_vec(),
+        }
+    }
+}
+
+/// Authorisation for one user to use one application through the portal
+#[derive(Debug, Clone)]
+pub struct ClientlessGrant {
+    pub id: String,
+    pub session_id: String,
+    pub app_id: String,
+    pub identity: Identity,
+    pub allowed_actions: Vec<AllowedAction>,
+    /// View-only: no input, no writes
+    pub read_only: bool,
+    pub conditions: Vec<AccessCondition>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub recording_id: Option<String>,
+}
+
+impl ClientlessGrant {
+    pub fn allows(&self, action: AllowedAction) -> bool {
+        if self.read_only && !matches!(action, AllowedAction::Read | AllowedAction::Download) {
+            return false;
+        }
+        self.allowed_actions.contains(&action)
+    }
+}
+
+/// Where a gateway writes its part of the session recording
+#[derive(Clone)]
+pub struct RecordingSink {
+    pub recorder: Arc<EnhancedSessionRecorder>,
+    pub recording_id: String,
+}
+
+impl ClientlessGateway {
+    pub fn new(access: Arc<ZeroTrustGateway>) -> Self {
+        Self {
+            access,
+            recorder: None,
+            http: HttpGateway::new(),
+            ssh: SshGateway::new(),
+            rdp: RdpGateway::default(),
+            grants: dashmap::DashMap::new(),
+        }
+    }
+
+    /// Record sessions of applications whose policy requires it
+    pub fn with_recorder(mut self, recorder: Arc<EnhancedSessionRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn with_ssh_gateway(mut self, ssh: SshGateway) -> Self {
+        self.ssh = ssh;
+        self
+    }
+
+    pub fn with_rdp_gateway(mut self, rdp: RdpGateway) -> Self {
+        self.rdp = rdp;
+        self
+    }
+
+    /// Evaluate access to an application and issue a portal grant
+    pub async fn authorize(
+        &self,
+        request: AccessRequest,
+        app: &ConnectedApp,
+    ) -> Result<ClientlessGrant, ClientlessError> {
+        if request.resource.id != app.id {
+            return Err(ClientlessError::Unauthorized);
+        }
+        let identity = request.identity.clone();
+        let risk_score = request.context.risk_score;
+
+        let decision = self.access.request_access(request).await;
+        match decision.decision {
+            Decision::Allow => {}
+            Decision::Challenge | Decision::StepUp => {
+                return Err(ClientlessError::StepUpRequired(decision.reasons.join(", ")));
+            }
+            Decision::Deny | Decision::Review => {
+                return Err(ClientlessError::Denied(decision.reasons.join(", ")));
+            }
+        }
+
+        let session_id = decision.session_id.clone().ok_or(ClientlessError::Unauthorized)?;
+        let session = self.access.session(&session_id).ok_or(ClientlessError::Unauthorized)?;
+
+        // Application policy on top of the ZTNA decision
+        if app.access_policy.require_mfa && !identity.mfa_verified {
+            return Err(ClientlessError::StepUpRequired("application requires MFA".to_string()));
+        }
+        let trust_score = 100.0 - risk_score.max(session.risk_score);
+        if trust_score < app.access_policy.min_trust_score {
+            return Err(ClientlessError::InsufficientTrust);
+        }
+
+        let mut read_only = false;
+        let mut expires_at = decision.expires_at.unwrap_or(session.expires_at);
+        for condition in &decision.conditions {
+            match condition {
+                AccessCondition::RequireMfa | AccessCondition::RequirePhishingResistantMfa
+                    if !identity.mfa_verified =>
+                {
+                    return Err(ClientlessError::StepUpRequired("MFA required".to_string()));
+                }
+                AccessCondition::RequireApproval { approver } => {
+                    return Err(ClientlessError::Denied(format!("approval required from {}", approver)));
+                }
+                AccessCondition::ReadOnly => read_only = true,
+                AccessCondition::SessionTimeout { minutes } => {
+                    expires_at = expires_at.min(Utc::now() + chrono::Duration::minutes(*minutes as i64));
+                }
+                _ => {}
+            }
+        }
+
+        let recording_id = if app.access_policy.record_session {
+            let recorder = self.recorder.as_ref().ok_or(ClientlessError::RecordingUnavailable)?;
+            let recording_type = match app.app_type {
+                AppType::Web { .. } => RecordingType::Network,
+                AppType::Ssh => RecordingType::Full,
+                AppType::Rdp | AppType::Vnc => RecordingType::ScreenOnly,
+                _ => RecordingType::Network,
+            };
+            let id = recorder.start(&session, recording_type).await;
+            recorder.note_app(&id, &app.id);
+            Some(id)
+        } else {
+            None
+        };
+
+        let grant = ClientlessGrant {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id,
+            app_id: app.id.clone(),
+            identity,
+            allowed_actions: app.access_policy.allowed_actions.clone(),
+            read_only,
+            conditions: decision.conditions,
+            issued_at: Utc::now(),
+            expires_at,
+            recording_id,
+        };
+        self.grants.insert(grant.id.clone(), grant.clone());
+
+        tracing::info!(
+            "Clientless grant {} for user {} to {} (read_only={}, recorded={})",
+            grant.id, grant.identity.user_id, app.name, grant.read_only, grant.recording_id.is_some()
+        );
+
+        Ok(grant)
+    }
+
+    /// Current grant, if it and its ZTNA session are still valid
+    pub async fn validate(&self, grant_id: &str, app: &ConnectedApp) -> Result<ClientlessGrant, ClientlessError> {
+        let grant = self.grants.get(grant_id)
+            .map(|g| g.clone())
+            .ok_or(ClientlessError::GrantNotFound)?;
+        if grant.app_id != app.id {
+            return Err(ClientlessError::Unauthorized);
+        }
+
+        let session = self.access.session(&grant.session_id)
+            .filter(|s| s.status == SessionStatus::Active && s.expires_at > Utc::now());
+        let Some(session) = session.filter(|_| grant.expires_at > Utc::now()) else {
+            self.end(grant_id).await;
+            return Err(ClientlessError::GrantExpired);
+        };
+
+        // Raised session risk holds the grant until the user passes step-up
+        if let Some(step_up) = self.access.step_up() {
+            match step_up.enforce(&session).await {
+                StepUpGate::Proceed => {}
+                StepUpGate::Challenge(challenge) => {
+                    return Err(ClientlessError::StepUpPending(challenge.id));
+                }
+                StepUpGate::Terminate(reason) => {
+                    self.end(grant_id).await;
+                    return Err(ClientlessError::Denied(reason));
+                }
+            }
+        }
+
+        Ok(grant)
+    }
+
+    /// End a grant and its recording
+    pub async fn end(&self, grant_id: &str) {
+        if let Some((_, grant)) = self.grants.remove(grant_id) {
+            if let (Some(recorder), Some(recording_id)) = (&self.recorder, &grant.recording_id) {
+                recorder.stop(recording_id).await;
+            }
+            tracing::info!("Clientless grant {} ended", grant_id);
+        }
+    }
+
+    /// End every grant issued under a ZTNA session
+    pub async fn end_session(&self, session_id: &str) {
+        let grant_ids: Vec<String> = self.grants.iter()
+            .filter(|g| g.session_id == session_id)
+            .map(|g| g.id.clone())
+            .collect();
+        for grant_id in grant_ids {
+            self.end(&grant_id).await;
+        }
+    }
+
+    fn sink(&self, grant: &ClientlessGrant) -> Option<RecordingSink> {
+        Some(RecordingSink {
+            recorder: self.recorder.clone()?,
+            recording_id: grant.recording_id.clone()?,
+        })
+    }
+
+    /// Handle browser-based web application access
+    pub async fn handle_web_access(
+        &self,
+        grant_id: &str,
+        app: &ConnectedApp,
+        request: HttpRequest,
+    ) -> Result<HttpResponse, ClientlessError> {
+        let grant = self.validate(grant_id, app).await?;
+
+        let action = if request.method.eq_ignore_ascii_case("GET") || request.method.eq_ignore_ascii_case("HEAD") {
+            AllowedAction::Read
+        } else if http::is_upload(&request) {
+            AllowedAction::Upload
+        } else {
+            AllowedAction::Write
+        };
+        if !grant.allows(action) {
+            return Ok(HttpResponse::forbidden());
+        }
+
+        let response = self.http.proxy(&grant, app, &request).await?;
+
+        if http::is_download(&response) && !grant.allows(AllowedAction::Download) {
+            return Ok(HttpResponse::forbidden());
+        }
+
+        // DLP scanning
+        if app.access_policy.dlp_enabled {
+            self.scan_response(&grant.session_id, &response).await?;
+        }
+
+        if let Some(sink) = self.sink(&grant) {
+            http::record_exchange(&sink, &grant, &request, &response).await;
+        }
+        self.log_access(&grant, app, &request).await;
+
+        Ok(response)
+    }
+
+    async fn scan_response(&self, session_id: &str, response: &HttpResponse) -> Result<(), ClientlessError> {
+        // DLP scanning of response content
+        let content = String::from_utf8_lossy(&response.body);
+
+        // Check for sensitive patterns
+        if self.contains_sensitive_data(&content) {
+            tracing::warn!("DLP: Sensitive data detected in session {}", session_id);
+            // Could block or redact
+        }
+
+        Ok(())
+    }
+
+    fn contains_sensitive_data(&self, content: &str) -> bool {
+        // Simple pattern checks
+        let patterns = [
+            r"\d{4}[- ]?\d{4}[- ]?\d{4}[- ]?\d{4}", // Credit card
+            r"\d{3}-\d{2}-\d{4}", // SSN
+        ];
+
+        for pattern in patterns {
+            if regex::Regex::new(pattern).ok().map(|r| r.is_match(content)).unwrap_or(false) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    async fn log_access(&self, grant: &ClientlessGrant, app: &ConnectedApp, request: &HttpRequest) {
+        tracing::info!(
+            "Clientless access: session={} app={} {} {}",
+            grant.session_id, app.name, request.method, request.path
+        );
+    }
+
+    /// Handle SSH via browser (terminal emulation)
+    pub async fn handle_ssh_access(
+        &self,
+        grant_id: &str,
+        app: &ConnectedApp,
+        terminal_cols: u16,
+        terminal_rows: u16,
+    ) -> Result<TerminalSession, ClientlessError> {
+        if !matches!(app.app_type, AppType::Ssh) {
+            return Err(ClientlessError::ProtocolMismatch);
+        }
+        let grant = self.validate(grant_id, app).await?;
+
+        tracing::info!(
+            "Starting SSH session for user {} to {}",
+            grant.identity.user_id, app.internal_host
+        );
+
+        let info = SshSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: grant.session_id.clone(),
+            app_id: app.id.clone(),
+            terminal_cols,
+            terminal_rows,
+            recording_enabled: grant.recording_id.is_some(),
+        };
+        self.ssh.open(info, &grant, app, self.sink(&grant)).await
+    }
+
+    /// Handle RDP via browser
+    pub async fn handle_rdp_access(
+        &self,
+        grant_id: &str,
+        app: &ConnectedApp,
+        width: u32,
+        height: u32,
+    ) -> Result<GuacamoleSession, ClientlessError> {
+        if !matches!(app.app_type, AppType::Rdp) {
+            return Err(ClientlessError::ProtocolMismatch);
+        }
+        let grant = self.validate(grant_id, app).await?;
+
+        tracing::info!(
+            "Starting RDP session for user {} to {}",
+            grant.identity.user_id, app.internal_host
+        );
+
+        let info = RdpSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: grant.session_id.clone(),
+            app_id: app.id.clone(),
+            width,
+            height,
+            recording_enabled: grant.recording_id.is_some(),
+        };
+        self.rdp.open(info, &grant, app, self.sink(&grant)).await
+    }
+}
+
+#[derive(Clone)]
+pub struct SshSession {
+    pub id: String,
+    pub session_id: String,
+    pub app_id: String,
+    pub terminal_cols: u16,
+    pub terminal_rows: u16,
+    pub recording_enabled: bool,
+}
+
+#[derive(Clone)]
+pub struct RdpSession {
+    pub id: String,
+    pub session_id: String,
+    pub app_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub recording_enabled: bool,
+}
+
+#[derive(Debug)]
+pub enum ClientlessError {
+    Unauthorized,
+    InsufficientTrust,
+    ProtocolMismatch,
+    ProxyError,
+    DlpBlocked,
+    Denied(String),
+    StepUpRequired(String),
+    /// Mid-session step-up challenge awaiting an answer
+    StepUpPending(String),
+    GrantNotFound,
+    GrantExpired,
+    /// Policy requires recording but no recorder is configured
+    RecordingUnavailable,
+    MissingCredential,
+    HostKeyMismatch,
+    /// Known-hosts file unreadable or not writable
+    HostKeyStore(String),
+    AuthenticationFailed,
+    Upstream(String),
+    Protocol(String),
+}
+
+impl std::fmt::Display for ClientlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized => write!(f, "Unauthorized"),
+            Self::InsufficientTrust => write!(f, "Insufficient trust score"),
+            Self::ProtocolMismatch => write!(f, "Protocol mismatch"),
+            Self::ProxyError => write!(f, "Proxy error"),
+            Self::DlpBlocked => write!(f, "Blocked by DLP policy"),
+            Self::Denied(reason) => write!(f, "Access denied: {}", reason),
+            Self::StepUpRequired(reason) => write!(f, "Step-up required: {}", reason),
+            Self::StepUpPending(challenge_id) => write!(f, "Step-up challenge pending: {}", challenge_id),
+            Self::GrantNotFound => write!(f, "Grant not found"),
+            Self::GrantExpired => write!(f, "Grant expired"),
+            Self::RecordingUnavailable => write!(f, "Session recording required but unavailable"),
+            Self::MissingCredential => write!(f, "No credential configured for application"),
+            Self::HostKeyMismatch => write!(f, "Host key mismatch"),
+            Self::HostKeyStore(e) => write!(f, "Known hosts unavailable: {}", e),
+            Self::AuthenticationFailed => write!(f, "Authentication to application failed"),
+            Self::Upstream(e) => write!(f, "Upstream error: {}", e),
+            Self::Protocol(e) => write!(f, "Protocol error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientlessError {}
@@ -0,0 +1,515 @@
+//! Browser Access Portal
+//!
+//! The front door for web applications without the agent. After the user
+//! signs in (usually through [`crate::sso`]) the login handler binds the
+//! resulting ZTNA session to a portal cookie; from then on every browser
+//! request is routed to the published application whose path prefix it
+//! falls under, authorised on first use with a `request_access` decision
+//! and proxied through [`super::HttpGateway`].
+//!
+//! The cookie is only a random handle. Everything it stands for lives
+//! here and dies with the ZTNA session: a revoked or expired session turns
+//! the cookie into a redirect to the login page.
+
+use super::http::PORTAL_COOKIE;
+use super::{AppType, ClientlessError, ClientlessGateway, ConnectedApp, HttpRequest, HttpResponse};
+use crate::{
+    AccessAction, AccessContext, AccessRequest, DataSensitivity, GeoLocation, NetworkType,
+    Resource, ResourceType, Session, SessionStatus,
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Portal configuration
+#[derive(Debug, Clone)]
+pub struct PortalConfig {
+    /// Sign-in page unauthenticated browsers are sent to
+    pub login_path: String,
+    /// Bindings unused for this long are dropped
+    pub idle_timeout: Duration,
+    /// Mark the cookie `Secure`; only off for plain-HTTP test setups
+    pub secure_cookie: bool,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        Self {
+            login_path: "/portal/login".to_string(),
+            idle_timeout: Duration::minutes(30),
+            secure_cookie: true,
+        }
+    }
+}
+
+/// A web application published on the portal
+#[derive(Clone)]
+pub struct PublishedApp {
+    pub app: ConnectedApp,
+    pub sensitivity: DataSensitivity,
+    pub owner: String,
+    /// Resource tags policies can match on
+    pub tags: HashMap<String, String>,
+}
+
+impl PublishedApp {
+    pub fn new(app: ConnectedApp) -> Self {
+        Self {
+            app,
+            sensitivity: DataSensitivity::Internal,
+            owner: String::new(),
+            tags: HashMap::new(),
+        }
+    }
+
+    pub fn with_sensitivity(mut self, sensitivity: DataSensitivity) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = owner.to_string();
+        self
+    }
+
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Path prefix the app is published under, without a trailing slash
+    pub fn path_prefix(&self) -> &str {
+        match &self.app.app_type {
+            AppType::Web { path_prefix } => path_prefix.trim_end_matches('/'),
+            _ => "",
+        }
+    }
+
+    fn resource(&self) -> Resource {
+        Resource {
+            id: self.app.id.clone(),
+            name: self.app.name.clone(),
+            resource_type: ResourceType::Application,
+            sensitivity: self.sensitivity,
+            owner: self.owner.clone(),
+            tags: self.tags.clone(),
+            access_policy: None,
+        }
+    }
+}
+
+/// Where a browser request comes from, as the listener sees it
+#[derive(Debug, Clone)]
+pub struct PortalClient {
+    pub client_ip: IpAddr,
+    pub geo_location: Option<GeoLocation>,
+    pub network_type: NetworkType,
+}
+
+/// A portal cookie bound to a ZTNA session
+#[derive(Debug, Clone)]
+pub struct PortalBinding {
+    pub session_id: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Clientless grant per application ID
+    pub grants: HashMap<String, String>,
+}
+
+/// Identity-aware reverse proxy for published web applications
+pub struct ClientlessPortal {
+    gateway: Arc<ClientlessGateway>,
+    config: PortalConfig,
+    apps: parking_lot::RwLock<Vec<PublishedApp>>,
+    /// Bindings keyed by SHA-256 of the cookie token
+    bindings: dashmap::DashMap<String, PortalBinding>,
+}
+
+impl ClientlessPortal {
+    pub fn new(gateway: Arc<ClientlessGateway>, config: PortalConfig) -> Self {
+        Self {
+            gateway,
+            config,
+            apps: parking_lot::RwLock::new(Vec::new()),
+            bindings: dashmap::DashMap::new(),
+        }
+    }
+
+    // =========================================================================
+    // Application catalogue
+    // =========================================================================
+
+    /// Publish a web application under its path prefix
+    pub fn publish(&self, published: PublishedApp) -> Result<(), ClientlessError> {
+        let prefix = published.path_prefix().to_string();
+        if !matches!(published.app.app_type, AppType::Web { .. }) {
+            return Err(ClientlessError::ProtocolMismatch);
+        }
+        if !prefix.starts_with('/') || prefix.len() < 2 || prefix.starts_with(&self.config.login_path) {
+            return Err(ClientlessError::Protocol(format!("invalid path prefix '{}'", prefix)));
+        }
+
+        let mut apps = self.apps.write();
+        if apps.iter().any(|a| a.path_prefix() == prefix && a.app.id != published.app.id) {
+            return Err(ClientlessError::Protocol(format!("path prefix '{}' already published", prefix)));
+        }
+        apps.retain(|a| a.app.id != published.app.id);
+        tracing::info!("Published {} at {}", published.app.name, prefix);
+        apps.push(published);
+        Ok(())
+    }
+
+    /// Withdraw an application and end the grants issued for it
+    pub async fn unpublish(&self, app_id: &str) -> bool {
+        let removed = {
+            let mut apps = self.apps.write();
+            let before = apps.len();
+            apps.retain(|a| a.app.id != app_id);
+            apps.len() != before
+        };
+
+        let grant_ids: Vec<String> = self.bindings.iter_mut()
+            .filter_map(|mut b| b.grants.remove(app_id))
+            .collect();
+        for grant_id in grant_ids {
+            self.gateway.end(&grant_id).await;
+        }
+        removed
+    }
+
+    pub fn apps(&self) -> Vec<PublishedApp> {
+        self.apps.read().clone()
+    }
+
+    /// Name and URL of every published application, for the landing page
+    pub fn catalogue(&self) -> Vec<(String, String)> {
+        self.apps.read().iter()
+            .map(|a| (a.app.name.clone(), format!("{}/", a.path_prefix())))
+            .collect()
+    }
+
+    /// The application a path belongs to; longest prefix wins
+    pub fn route(&self, path: &str) -> Option<PublishedApp> {
+        let path = path.split('?').next().unwrap_or(path);
+        self.apps.read().iter()
+            .filter(|a| {
+                let prefix = a.path_prefix();
+                path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|a| a.path_prefix().len())
+            .cloned()
+    }
+
+    // =========================================================================
+    // Session binding
+    // =========================================================================
+
+    /// Bind a signed-in ZTNA session to a new portal cookie. Returns the
+    /// `Set-Cookie` value for the login response.
+    pub fn bind(&self, session: &Session) -> Result<String, ClientlessError> {
+        if session.status != SessionStatus::Active || session.expires_at <= Utc::now() {
+            return Err(ClientlessError::GrantExpired);
+        }
+
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+
+        let now = Utc::now();
+        self.bindings.insert(token_key(&token), PortalBinding {
+            session_id: session.id.clone(),
+            user_id: session.identity.user_id.clone(),
+            created_at: now,
+            last_seen: now,
+            grants: HashMap::new(),
+        });
+        tracing::info!("Portal bound to session {} for {}", session.id, session.identity.user_id);
+
+        let max_age = (session.expires_at - now).num_seconds().max(0);
+        Ok(self.cookie(&token, max_age))
+    }
+
+    /// End the binding behind a request's cookie and its grants. Returns
+    /// the `Set-Cookie` value that clears the cookie.
+    pub async fn logout(&self, request: &HttpRequest) -> String {
+        if let Some(token) = portal_token(request) {
+            if let Some((_, binding)) = self.bindings.remove(&token_key(&token)) {
+                self.end_grants(&binding).await;
+                tracing::info!("Portal logout for session {}", binding.session_id);
+            }
+        }
+        self.cookie("", 0)
+    }
+
+    /// Drop every binding of a ZTNA session, e.g. on termination
+    pub async fn unbind_session(&self, session_id: &str) {
+        let keys: Vec<String> = self.bindings.iter()
+            .filter(|b| b.session_id == session_id)
+            .map(|b| b.key().clone())
+            .collect();
+        for key in keys {
+            if let Some((_, binding)) = self.bindings.remove(&key) {
+                self.end_grants(&binding).await;
+            }
+        }
+    }
+
+    /// Drop bindings whose session has ended or that sat idle too long
+    pub async fn prune(&self) -> usize {
+        let now = Utc::now();
+        let access = &self.gateway.access;
+        let stale: Vec<String> = self.bindings.iter()
+            .filter(|b| {
+                b.last_seen + self.config.idle_timeout <= now
+                    || !access.session(&b.session_id)
+                        .is_some_and(|s| s.status == SessionStatus::Active && s.expires_at > now)
+            })
+            .map(|b| b.key().clone())
+            .collect();
+
+        let mut pruned = 0;
+        for key in stale {
+            if let Some((_, binding)) = self.bindings.remove(&key) {
+                self.end_grants(&binding).await;
+                pruned += 1;
+            }
+        }
+        pruned
+    }
+
+    /// Run [`Self::prune`] on an interval
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                let pruned = self.prune().await;
+                if pruned > 0 {
+                    tracing::debug!("Pruned {} portal bindings", pruned);
+                }
+            }
+        })
+    }
+
+    // =========================================================================
+    // Request handling
+    // =========================================================================
+
+    /// Serve one browser request
+    pub async fn handle(&self, request: HttpRequest, client: &PortalClient) -> HttpResponse {
+        let Some(published) = self.route(&request.path) else {
+            return respond(404, "Not Found");
+        };
+        // The bare prefix would break relative links in the app
+        if request.path.split('?').next() == Some(published.path_prefix()) {
+            return redirect(&format!("{}/", published.path_prefix()), Vec::new());
+        }
+
+        let Some(key) = portal_token(&request).map(|t| token_key(&t)) else {
+            return self.to_login(&request.path, Vec::new());
+        };
+        let Some(session) = self.live_session(&key) else {
+            return self.to_login(&request.path, vec![self.cookie("", 0)]);
+        };
+
+        // One retry: a grant that lapsed since the last request is replaced
+        for _ in 0..2 {
+            let grant_id = match self.bindings.get(&key).and_then(|b| b.grants.get(&published.app.id).cloned()) {
+                Some(grant_id) => grant_id,
+                None => match self.authorize(&key, &session, &published, &request, client).await {
+                    Ok(grant_id) => grant_id,
+                    Err(e) => return self.error_response(&request.path, e),
+                },
+            };
+
+            match self.gateway.handle_web_access(&grant_id, &published.app, request.clone()).await {
+                Err(ClientlessError::GrantExpired | ClientlessError::GrantNotFound) => {
+                    if let Some(mut binding) = self.bindings.get_mut(&key) {
+                        binding.grants.remove(&published.app.id);
+                    }
+                    if self.live_session(&key).is_none() {
+                        return self.to_login(&request.path, vec![self.cookie("", 0)]);
+                    }
+                }
+                Ok(response) => return response,
+                Err(e) => return self.error_response(&request.path, e),
+            }
+        }
+        respond(403, "Forbidden")
+    }
+
+    /// The binding's session if it is still usable; drops the binding
+    /// otherwise
+    fn live_session(&self, key: &str) -> Option<Session> {
+        let now = Utc::now();
+        let session_id = {
+            let mut binding = self.bindings.get_mut(key)?;
+            if binding.last_seen + self.config.idle_timeout > now {
+                binding.last_seen = now;
+                Some(binding.session_id.clone())
+            } else {
+                None
+            }
+        };
+
+        let session = session_id
+            .and_then(|id| self.gateway.access.session(&id))
+            .filter(|s| s.status == SessionStatus::Active && s.expires_at > now);
+        if session.is_none() {
+            self.bindings.remove(key);
+        }
+        session
+    }
+
+    /// Ask for access to an app on the session's behalf and remember the
+    /// grant on the binding
+    async fn authorize(
+        &self,
+        key: &str,
+        session: &Session,
+        published: &PublishedApp,
+        request: &HttpRequest,
+        client: &PortalClient,
+    ) -> Result<String, ClientlessError> {
+        let user_agent = request.headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default();
+
+        let access_request = AccessRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            identity: session.identity.clone(),
+            device: session.device.clone(),
+            resource: published.resource(),
+            action: AccessAction::Connect,
+            context: AccessContext {
+                client_ip: client.client_ip,
+                geo_location: client.geo_location.clone(),
+                network_type: client.network_type,
+                time_of_access: Utc::now(),
+                session_id: Some(session.id.clone()),
+                user_agent,
+                risk_score: session.risk_score,
+                signals: Vec::new(),
+            },
+            timestamp: Utc::now(),
+        };
+
+        let grant = self.gateway.authorize(access_request, &published.app).await?;
+        if grant.session_id != session.id {
+            // The decision opened a different session; the cookie must not
+            // outlive the one it was bound to
+            self.gateway.end(&grant.id).await;
+            return Err(ClientlessError::Unauthorized);
+        }
+
+        let replaced = match self.bindings.get_mut(key) {
+            Some(mut binding) => binding.grants.insert(published.app.id.clone(), grant.id.clone()),
+            None => {
+                self.gateway.end(&grant.id).await;
+                return Err(ClientlessError::GrantExpired);
+            }
+        };
+        // A concurrent request authorised the same app first
+        if let Some(previous) = replaced {
+            self.gateway.end(&previous).await;
+        }
+        Ok(grant.id)
+    }
+
+    async fn end_grants(&self, binding: &PortalBinding) {
+        for grant_id in binding.grants.values() {
+            self.gateway.end(grant_id).await;
+        }
+    }
+
+    fn error_response(&self, path: &str, error: ClientlessError) -> HttpResponse {
+        match &error {
+            ClientlessError::StepUpRequired(_) | ClientlessError::StepUpPending(_) => {
+                tracing::debug!("Portal step-up for {}: {}", path, error);
+                self.to_login(path, Vec::new())
+            }
+            ClientlessError::Upstream(_) | ClientlessError::ProxyError => respond(502, "Bad Gateway"),
+            ClientlessError::RecordingUnavailable | ClientlessError::HostKeyStore(_) => {
+                respond(503, "Service Unavailable")
+            }
+            e => {
+                tracing::info!("Portal refused {}: {}", path, e);
+                respond(403, "Forbidden")
+            }
+        }
+    }
+
+    fn to_login(&self, return_to: &str, set_cookies: Vec<String>) -> HttpResponse {
+        let location = format!("{}?return_to={}", self.config.login_path, percent_encode(return_to));
+        redirect(&location, set_cookies)
+    }
+
+    fn cookie(&self, token: &str, max_age: i64) -> String {
+        let mut cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+            PORTAL_COOKIE, token, max_age
+        );
+        if self.config.secure_cookie {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+}
+
+/// The portal cookie from a request's `Cookie` header
+fn portal_token(request: &HttpRequest) -> Option<String> {
+    let header = request.headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("cookie"))?
+        .1;
+    header.split(';')
+        .map(str::trim)
+        .find_map(|c| c.strip_prefix(PORTAL_COOKIE)?.strip_prefix('='))
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+/// Bindings are keyed by digest so a memory dump holds no live cookies
+fn token_key(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn respond(status: u16, body: &str) -> HttpResponse {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "text/plain; charset=utf-8".to_string());
+    HttpResponse {
+        status,
+        headers,
+        set_cookies: Vec::new(),
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+fn redirect(location: &str, set_cookies: Vec<String>) -> HttpResponse {
+    let mut headers = HashMap::new();
+    headers.insert("location".to_string(), location.to_string());
+    headers.insert("cache-control".to_string(), "no-store".to_string());
+    HttpResponse {
+        status: 302,
+        headers,
+        set_cookies,
+        body: Vec::new(),
+    }
+}
+
+/// Query-component encoding (RFC 3986 unreserved characters pass through)
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
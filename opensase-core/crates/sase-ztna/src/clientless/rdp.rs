@@ -0,0 +1,569 @@
+//! RDP via Guacamole
+//!
+//! guacd does the RDP work; the browser runs guacamole-common-js. The
+//! gateway sits between them as a protocol translator: it performs the
+//! guacd handshake itself (so credentials never reach the browser), then
+//! relays instructions in both directions, enforcing the grant (view-only,
+//! clipboard, file transfer, printing) and feeding the recording.
+//!
+//! Instructions are `LEN.VALUE,LEN.VALUE,...;` where the first element is
+//! the opcode and each LEN counts Unicode characters.
+
+use super::{
+    AllowedAction, ClientlessError, ClientlessGrant, ConnectedApp, CredentialSecret, RdpSession,
+    RecordingSink,
+};
+use crate::recording::{
+    ClipboardData, ClipboardOperation, FileOperation, FrameType, RecordedActivity, ScreenFrameData,
+};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// guacd's answer to a forbidden stream (CLIENT_FORBIDDEN)
+const STATUS_FORBIDDEN: &str = "771";
+
+/// Longest instruction accepted from either side
+const MAX_INSTRUCTION: usize = 8 * 1024 * 1024;
+
+/// One Guacamole protocol instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuacInstruction {
+    pub opcode: String,
+    pub args: Vec<String>,
+}
+
+impl GuacInstruction {
+    pub fn new(opcode: &str, args: &[&str]) -> Self {
+        Self {
+            opcode: opcode.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        for (i, element) in std::iter::once(&self.opcode).chain(self.args.iter()).enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&element.chars().count().to_string());
+            out.push('.');
+            out.push_str(element);
+        }
+        out.push(';');
+        out
+    }
+
+    fn arg(&self, index: usize) -> &str {
+        self.args.get(index).map(String::as_str).unwrap_or("")
+    }
+}
+
+/// Incremental instruction parser
+#[derive(Default)]
+pub struct GuacDecoder {
+    buffer: String,
+    /// Bytes of a UTF-8 sequence split across reads
+    partial: Vec<u8>,
+}
+
+impl GuacDecoder {
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        match std::str::from_utf8(&self.partial) {
+            Ok(text) => {
+                self.buffer.push_str(text);
+                self.partial.clear();
+            }
+            Err(e) => {
+                let valid = e.valid_up_to();
+                self.buffer.push_str(std::str::from_utf8(&self.partial[..valid]).unwrap_or_default());
+                if e.error_len().is_some() {
+                    // Not a split sequence, just invalid; skip it
+                    self.partial.clear();
+                } else {
+                    self.partial.drain(..valid);
+                }
+            }
+        }
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.buffer.push_str(text);
+    }
+
+    /// Next complete instruction, if buffered
+    pub fn next_instruction(&mut self) -> Result<Option<GuacInstruction>, ClientlessError> {
+        if self.buffer.len() > MAX_INSTRUCTION {
+            return Err(ClientlessError::Protocol("instruction too long".to_string()));
+        }
+
+        let mut elements = Vec::new();
+        let mut rest = self.buffer.as_str();
+        let consumed;
+        loop {
+            let Some(dot) = rest.find('.') else { return Ok(None) };
+            let len: usize = rest[..dot].parse()
+                .map_err(|_| ClientlessError::Protocol(format!("bad element length {:?}", &rest[..dot])))?;
+            let value_start = dot + 1;
+
+            // LEN is in characters; find the byte offset after LEN chars
+            let mut chars = rest[value_start..].char_indices();
+            let Some((offset, terminator)) = chars.nth(len) else { return Ok(None) };
+            let value_end = value_start + offset;
+            elements.push(rest[value_start..value_end].to_string());
+            rest = &rest[value_end + terminator.len_utf8()..];
+
+            match terminator {
+                ',' => continue,
+                ';' => {
+                    consumed = self.buffer.len() - rest.len();
+                    break;
+                }
+                other => {
+                    return Err(ClientlessError::Protocol(format!("unexpected {:?} after element", other)));
+                }
+            }
+        }
+
+        self.buffer.drain(..consumed);
+        let mut elements = elements.into_iter();
+        let opcode = elements.next().unwrap_or_default();
+        Ok(Some(GuacInstruction { opcode, args: elements.collect() }))
+    }
+}
+
+/// Live RDP session: text frames for a Guacamole WebSocket tunnel
+pub struct GuacamoleSession {
+    pub info: RdpSession,
+    /// guacd connection id
+    pub connection_id: String,
+    /// Instructions from the browser
+    pub to_server: mpsc::Sender<String>,
+    /// Instructions for the browser; the first is the tunnel UUID
+    pub from_server: mpsc::Receiver<String>,
+}
+
+/// RDP gateway backed by guacd
+#[derive(Debug, Clone)]
+pub struct RdpGateway {
+    pub guacd_addr: String,
+    pub handshake_timeout: Duration,
+    /// Extra RDP parameters, e.g. `security`, `ignore-cert`
+    pub parameters: HashMap<String, String>,
+}
+
+impl Default for RdpGateway {
+    fn default() -> Self {
+        let parameters = [("security", "any"), ("ignore-cert", "true"), ("resize-method", "display-update")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Self {
+            guacd_addr: "127.0.0.1:4822".to_string(),
+            handshake_timeout: Duration::from_secs(15),
+            parameters,
+        }
+    }
+}
+
+impl RdpGateway {
+    pub fn new(guacd_addr: &str) -> Self {
+        Self {
+            guacd_addr: guacd_addr.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_parameter(mut self, name: &str, value: &str) -> Self {
+        self.parameters.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Connection parameters for guacd, derived from the app and grant
+    fn connection_parameters(&self, info: &RdpSession, grant: &ClientlessGrant, app: &ConnectedApp) -> HashMap<String, String> {
+        let mut params = self.parameters.clone();
+        let mut set = |name: &str, value: String| {
+            params.insert(name.to_string(), value);
+        };
+
+        set("hostname", app.internal_host.clone());
+        set("port", app.internal_port.to_string());
+        set("width", info.width.to_string());
+        set("height", info.height.to_string());
+        if let Some(credential) = &app.credential {
+            set("username", credential.username.clone());
+            if let CredentialSecret::Password(password) = &credential.secret {
+                set("password", password.clone());
+            }
+            if let Some(domain) = &credential.domain {
+                set("domain", domain.clone());
+            }
+        }
+
+        // guacd enforces these too; the translator is a second line
+        set("read-only", grant.read_only.to_string());
+        set("disable-copy", (!grant.allows(AllowedAction::Clipboard)).to_string());
+        set("disable-paste", (!grant.allows(AllowedAction::Clipboard)).to_string());
+        set("enable-printing", grant.allows(AllowedAction::Print).to_string());
+        let transfer = grant.allows(AllowedAction::Upload) || grant.allows(AllowedAction::Download);
+        set("enable-drive", transfer.to_string());
+        set("disable-upload", (!grant.allows(AllowedAction::Upload)).to_string());
+        set("disable-download", (!grant.allows(AllowedAction::Download)).to_string());
+        params
+    }
+
+    pub async fn open(
+        &self,
+        info: RdpSession,
+        grant: &ClientlessGrant,
+        app: &ConnectedApp,
+        sink: Option<RecordingSink>,
+    ) -> Result<GuacamoleSession, ClientlessError> {
+        if app.credential.is_none() {
+            return Err(ClientlessError::MissingCredential);
+        }
+
+        let params = self.connection_parameters(&info, grant, app);
+        let (guacd, decoder, connection_id) = tokio::time::timeout(
+            self.handshake_timeout,
+            handshake(&self.guacd_addr, &info, &params),
+        )
+        .await
+        .map_err(|_| ClientlessError::Upstream("guacd handshake timed out".to_string()))??;
+
+        let (to_server_tx, to_server_rx) = mpsc::channel(256);
+        let (from_server_tx, from_server_rx) = mpsc::channel(256);
+
+        // guacamole-common-js expects the tunnel UUID first
+        let _ = from_server_tx.send(GuacInstruction::new("", &[&info.id]).encode()).await;
+
+        let translator = Translator {
+            info: info.clone(),
+            read_only: grant.read_only,
+            clipboard: grant.allows(AllowedAction::Clipboard),
+            upload: grant.allows(AllowedAction::Upload),
+            download: grant.allows(AllowedAction::Download),
+            expires_at: grant.expires_at,
+            sink,
+            blocked_streams: HashSet::new(),
+        };
+        tokio::spawn(translator.run(guacd, decoder, to_server_rx, from_server_tx));
+
+        tracing::info!(
+            "RDP session {} to {}:{} via guacd connection {}",
+            info.id, app.internal_host, app.internal_port, connection_id
+        );
+        Ok(GuacamoleSession {
+            info,
+            connection_id,
+            to_server: to_server_tx,
+            from_server: from_server_rx,
+        })
+    }
+}
+
+/// `select` → `args` → client capabilities + `connect` → `ready`
+async fn handshake(
+    guacd_addr: &str,
+    info: &RdpSession,
+    params: &HashMap<String, String>,
+) -> Result<(TcpStream, GuacDecoder, String), ClientlessError> {
+    let mut guacd = TcpStream::connect(guacd_addr).await
+        .map_err(|e| ClientlessError::Upstream(format!("guacd {}: {}", guacd_addr, e)))?;
+    let mut decoder = GuacDecoder::default();
+
+    send(&mut guacd, &GuacInstruction::new("select", &["rdp"])).await?;
+    let args = expect(&mut guacd, &mut decoder, "args").await?;
+
+    let width = info.width.to_string();
+    let height = info.height.to_string();
+    let mut setup = GuacInstruction::new("size", &[&width, &height, "96"]).encode();
+    setup.push_str(&GuacInstruction::new("audio", &[]).encode());
+    setup.push_str(&GuacInstruction::new("video", &[]).encode());
+    setup.push_str(&GuacInstruction::new("image", &["image/png", "image/jpeg", "image/webp"]).encode());
+
+    // Values in the order guacd listed the argument names; a leading
+    // VERSION_x_y_z element is echoed back
+    let values: Vec<String> = args.args.iter()
+        .map(|name| {
+            if name.starts_with("VERSION_") {
+                name.clone()
+            } else {
+                params.get(name).cloned().unwrap_or_default()
+            }
+        })
+        .collect();
+    setup.push_str(&GuacInstruction { opcode: "connect".to_string(), args: values }.encode());
+    guacd.write_all(setup.as_bytes()).await
+        .map_err(|e| ClientlessError::Upstream(e.to_string()))?;
+
+    let ready = expect(&mut guacd, &mut decoder, "ready").await?;
+    Ok((guacd, decoder, ready.arg(0).to_string()))
+}
+
+async fn send(guacd: &mut TcpStream, instruction: &GuacInstruction) -> Result<(), ClientlessError> {
+    guacd.write_all(instruction.encode().as_bytes()).await
+        .map_err(|e| ClientlessError::Upstream(e.to_string()))
+}
+
+async fn expect(
+    guacd: &mut TcpStream,
+    decoder: &mut GuacDecoder,
+    opcode: &str,
+) -> Result<GuacInstruction, ClientlessError> {
+    let mut buf = vec![0u8; 8192];
+    loop {
+        if let Some(instruction) = decoder.next_instruction()? {
+            if instruction.opcode == opcode {
+                return Ok(instruction);
+            }
+            if instruction.opcode == "error" {
+                return Err(ClientlessError::Upstream(format!("guacd: {}", instruction.arg(0))));
+            }
+            continue;
+        }
+        let n = guacd.read(&mut buf).await
+            .map_err(|e| ClientlessError::Upstream(e.to_string()))?;
+        if n == 0 {
+            return Err(ClientlessError::Upstream("guacd closed during handshake".to_string()));
+        }
+        decoder.push_bytes(&buf[..n]);
+    }
+}
+
+/// What to do with one instruction
+enum Verdict {
+    Forward,
+    Drop,
+    /// Drop and answer the sender with this instruction
+    Refuse(GuacInstruction),
+}
+
+struct Translator {
+    info: RdpSession,
+    read_only: bool,
+    clipboard: bool,
+    upload: bool,
+    download: bool,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    sink: Option<RecordingSink>,
+    /// Stream indexes whose `blob`/`end` must not pass
+    blocked_streams: HashSet<(bool, String)>,
+}
+
+impl Translator {
+    async fn run(
+        mut self,
+        guacd: TcpStream,
+        mut guacd_decoder: GuacDecoder,
+        mut from_browser: mpsc::Receiver<String>,
+        to_browser: mpsc::Sender<String>,
+    ) {
+        let (mut guacd_reader, mut guacd_writer) = guacd.into_split();
+        let mut browser_decoder = GuacDecoder::default();
+        let mut buf = vec![0u8; 64 * 1024];
+
+        let remaining = (self.expires_at - chrono::Utc::now()).to_std().unwrap_or_default();
+        let deadline = tokio::time::sleep(remaining);
+        tokio::pin!(deadline);
+
+        'session: loop {
+            tokio::select! {
+                read = guacd_reader.read(&mut buf) => {
+                    let n = match read {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => n,
+                    };
+                    guacd_decoder.push_bytes(&buf[..n]);
+
+                    let mut batch = String::new();
+                    loop {
+                        let instruction = match guacd_decoder.next_instruction() {
+                            Ok(Some(instruction)) => instruction,
+                            Ok(None) => break,
+                            Err(e) => {
+                                tracing::warn!("RDP session {}: {}", self.info.id, e);
+                                break 'session;
+                            }
+                        };
+                        match self.from_server(&instruction).await {
+                            Verdict::Forward => batch.push_str(&instruction.encode()),
+                            Verdict::Drop => {}
+                            Verdict::Refuse(reply) => {
+                                if guacd_writer.write_all(reply.encode().as_bytes()).await.is_err() {
+                                    break 'session;
+                                }
+                            }
+                        }
+                    }
+                    if !batch.is_empty() {
+                        self.record_frame(&batch).await;
+                        if to_browser.send(batch).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                message = from_browser.recv() => {
+                    let Some(message) = message else { break };
+                    browser_decoder.push_str(&message);
+
+                    let mut batch = String::new();
+                    let mut reply = String::new();
+                    loop {
+                        let instruction = match browser_decoder.next_instruction() {
+                            Ok(Some(instruction)) => instruction,
+                            Ok(None) => break,
+                            Err(e) => {
+                                tracing::warn!("RDP session {}: {}", self.info.id, e);
+                                break 'session;
+                            }
+                        };
+                        if instruction.opcode == "disconnect" {
+                            break 'session;
+                        }
+                        match self.from_browser(&instruction).await {
+                            Verdict::Forward => batch.push_str(&instruction.encode()),
+                            Verdict::Drop => {}
+                            Verdict::Refuse(answer) => reply.push_str(&answer.encode()),
+                        }
+                    }
+                    if !batch.is_empty() && guacd_writer.write_all(batch.as_bytes()).await.is_err() {
+                        break;
+                    }
+                    if !reply.is_empty() && to_browser.send(reply).await.is_err() {
+                        break;
+                    }
+                }
+                _ = &mut deadline => {
+                    let notice = GuacInstruction::new("error", &["Access has expired", STATUS_FORBIDDEN]);
+                    let _ = to_browser.send(notice.encode()).await;
+                    break;
+                }
+            }
+        }
+
+        let _ = guacd_writer.write_all(GuacInstruction::new("disconnect", &[]).encode().as_bytes()).await;
+        let _ = guacd_writer.shutdown().await;
+        tracing::info!("RDP session {} closed", self.info.id);
+    }
+
+    /// Policy for instructions guacd sends towards the browser
+    async fn from_server(&mut self, instruction: &GuacInstruction) -> Verdict {
+        match instruction.opcode.as_str() {
+            // Remote copy
+            "clipboard" => {
+                if !self.clipboard {
+                    self.blocked_streams.insert((true, instruction.arg(0).to_string()));
+                    return Verdict::Refuse(forbidden_ack(instruction.arg(0)));
+                }
+                self.record_clipboard(ClipboardOperation::Copy, instruction.arg(1)).await;
+                Verdict::Forward
+            }
+            // Download offered by the remote side
+            "file" => {
+                if !self.download {
+                    self.blocked_streams.insert((true, instruction.arg(0).to_string()));
+                    return Verdict::Refuse(forbidden_ack(instruction.arg(0)));
+                }
+                self.record_file(FileOperation::Download, instruction.arg(2)).await;
+                Verdict::Forward
+            }
+            "blob" | "end" if self.blocked_streams.contains(&(true, instruction.arg(0).to_string())) => {
+                if instruction.opcode == "end" {
+                    self.blocked_streams.remove(&(true, instruction.arg(0).to_string()));
+                }
+                Verdict::Drop
+            }
+            "size" if instruction.arg(0) == "0" && instruction.args.len() >= 3 => {
+                // Default layer resized
+                self.info.width = instruction.arg(1).parse().unwrap_or(self.info.width);
+                self.info.height = instruction.arg(2).parse().unwrap_or(self.info.height);
+                Verdict::Forward
+            }
+            _ => Verdict::Forward,
+        }
+    }
+
+    /// Policy for instructions the browser sends towards guacd
+    async fn from_browser(&mut self, instruction: &GuacInstruction) -> Verdict {
+        match instruction.opcode.as_str() {
+            "key" => {
+                if self.read_only {
+                    return Verdict::Drop;
+                }
+                if instruction.arg(1) == "1" {
+                    self.record_key(instruction.arg(0)).await;
+                }
+                Verdict::Forward
+            }
+            "mouse" if self.read_only => Verdict::Drop,
+            // Local paste
+            "clipboard" => {
+                if self.read_only || !self.clipboard {
+                    self.blocked_streams.insert((false, instruction.arg(0).to_string()));
+                    return Verdict::Refuse(forbidden_ack(instruction.arg(0)));
+                }
+                self.record_clipboard(ClipboardOperation::Paste, instruction.arg(1)).await;
+                Verdict::Forward
+            }
+            // Upload
+            "file" | "put" => {
+                if self.read_only || !self.upload {
+                    self.blocked_streams.insert((false, instruction.arg(0).to_string()));
+                    return Verdict::Refuse(forbidden_ack(instruction.arg(0)));
+                }
+                let name = if instruction.opcode == "file" { instruction.arg(2) } else { instruction.arg(3) };
+                self.record_file(FileOperation::Upload, name).await;
+                Verdict::Forward
+            }
+            "blob" | "end" if self.blocked_streams.contains(&(false, instruction.arg(0).to_string())) => {
+                if instruction.opcode == "end" {
+                    self.blocked_streams.remove(&(false, instruction.arg(0).to_string()));
+                }
+                Verdict::Drop
+            }
+            _ => Verdict::Forward,
+        }
+    }
+
+    async fn record_frame(&self, batch: &str) {
+        let Some(sink) = &self.sink else { return };
+        sink.recorder.record(&sink.recording_id, RecordedActivity::ScreenFrame(ScreenFrameData {
+            timestamp: chrono::Utc::now(),
+            width: self.info.width,
+            height: self.info.height,
+            frame_type: FrameType::Delta,
+            data: batch.as_bytes().to_vec(),
+        })).await;
+    }
+
+    async fn record_key(&self, keysym: &str) {
+        let Some(sink) = &self.sink else { return };
+        let keysym = keysym.parse().unwrap_or(0);
+        sink.recorder.record_keystroke(&sink.recording_id, keysym, 0, "rdp").await;
+    }
+
+    async fn record_clipboard(&self, operation: ClipboardOperation, mimetype: &str) {
+        let Some(sink) = &self.sink else { return };
+        sink.recorder.record(&sink.recording_id, RecordedActivity::ClipboardAction(ClipboardData {
+            timestamp: chrono::Utc::now(),
+            operation,
+            content_type: mimetype.to_string(),
+            size_bytes: 0,
+        })).await;
+    }
+
+    async fn record_file(&self, operation: FileOperation, name: &str) {
+        let Some(sink) = &self.sink else { return };
+        sink.recorder.record_file_access(&sink.recording_id, operation, name, 0).await;
+    }
+}
+
+/// Refuse a stream the sender just opened
+fn forbidden_ack(stream: &str) -> GuacInstruction {
+    GuacInstruction::new("ack", &[stream, "Forbidden by policy", STATUS_FORBIDDEN])
+}
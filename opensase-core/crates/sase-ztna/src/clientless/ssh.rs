@@ -0,0 +1,608 @@
+//! SSH Terminal Bridge
+//!
+//! The gateway is the SSH client: it connects with the application's
+//! gateway-held credential, opens a PTY and shell, and exchanges terminal
+//! bytes with the browser terminal (xterm.js or similar) over channels the
+//! portal's WebSocket handler drives. Host keys are pinned per application
+//! or trusted on first use, optionally remembered in a known-hosts file. Input is dropped for read-only grants, paste
+//! needs the clipboard action, and keystrokes, commands and output are
+//! recorded when the grant is.
+
+use super::{
+    AllowedAction, ClientlessError, ClientlessGrant, ConnectedApp, CredentialSecret, RecordingSink,
+    SshSession,
+};
+use crate::recording::{
+    ClipboardData, ClipboardOperation, FrameType, RecordedActivity, ScreenFrameData,
+};
+use russh::client;
+use russh::ChannelMsg;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Longest command line kept for the recording
+const MAX_COMMAND_LEN: usize = 4096;
+
+/// Browser → gateway
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TerminalInput {
+    /// Typed keys as sent by the terminal emulator
+    Data { data: String },
+    /// Clipboard paste
+    Paste { data: String },
+    Resize { cols: u16, rows: u16 },
+}
+
+/// Gateway → browser
+#[derive(Debug, Clone)]
+pub enum TerminalOutput {
+    Data(Vec<u8>),
+    /// Gateway message to show the user (not from the server)
+    Notice(String),
+    Exit { status: Option<u32> },
+}
+
+/// Live bridged terminal
+pub struct TerminalSession {
+    pub info: SshSession,
+    pub input: mpsc::Sender<TerminalInput>,
+    pub output: mpsc::Receiver<TerminalOutput>,
+}
+
+struct HostKeyCheck {
+    host: String,
+    expected: Option<String>,
+    known_hosts: Arc<KnownHosts>,
+    rejection: Arc<parking_lot::Mutex<Option<ClientlessError>>>,
+}
+
+#[async_trait::async_trait]
+impl client::Handler for HostKeyCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        self,
+        server_public_key: &russh_keys::key::PublicKey,
+    ) -> Result<(Self, bool), Self::Error> {
+        let fingerprint = server_public_key.fingerprint();
+        let verdict = match &self.expected {
+            Some(pinned) if pinned.trim_start_matches("SHA256:") == fingerprint => Ok(()),
+            Some(_) => Err(ClientlessError::HostKeyMismatch),
+            None => self.known_hosts.check(&self.host, &fingerprint),
+        };
+        if let Err(e) = verdict {
+            tracing::error!("Rejected SSH host key SHA256:{} for {}: {}", fingerprint, self.host, e);
+            *self.rejection.lock() = Some(e);
+            return Ok((self, false));
+        }
+        Ok((self, true))
+    }
+}
+
+/// Host keys trusted on first use, `host:port` → SHA-256 fingerprint. With a
+/// file, each new pin is written through before the connection proceeds, so
+/// a restart can't turn a changed key back into a first use.
+struct KnownHosts {
+    hosts: dashmap::DashMap<String, String>,
+    path: Option<PathBuf>,
+    /// Held while a host is looked up and pinned
+    pin: parking_lot::Mutex<()>,
+}
+
+impl KnownHosts {
+    fn memory() -> Self {
+        Self { hosts: dashmap::DashMap::new(), path: None, pin: parking_lot::Mutex::new(()) }
+    }
+
+    /// Read `<host:port> SHA256:<fingerprint>` lines; a missing file is empty
+    fn load(path: PathBuf) -> Result<Self, ClientlessError> {
+        let hosts = dashmap::DashMap::new();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for (n, line) in content.lines().enumerate() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let mut fields = line.split_whitespace();
+                    match (fields.next(), fields.next(), fields.next()) {
+                        (Some(host), Some(fingerprint), None) => {
+                            hosts.insert(host.to_string(), fingerprint.trim_start_matches("SHA256:").to_string());
+                        }
+                        _ => {
+                            return Err(ClientlessError::HostKeyStore(format!(
+                                "{}:{}: expected '<host:port> SHA256:<fingerprint>'",
+                                path.display(),
+                                n + 1
+                            )));
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(store_error(&path, e)),
+        }
+        Ok(Self { hosts, path: Some(path), pin: parking_lot::Mutex::new(()) })
+    }
+
+    /// Accept a known key or pin an unknown one; a pin that can't be saved
+    /// is refused rather than trusted for this process only
+    fn check(&self, host: &str, fingerprint: &str) -> Result<(), ClientlessError> {
+        let _pin = self.pin.lock();
+        if let Some(known) = self.hosts.get(host) {
+            return if *known == fingerprint { Ok(()) } else { Err(ClientlessError::HostKeyMismatch) };
+        }
+
+        self.hosts.insert(host.to_string(), fingerprint.to_string());
+        if let Err(e) = self.save() {
+            self.hosts.remove(host);
+            return Err(e);
+        }
+        tracing::warn!("Trusting SSH host key SHA256:{} for {} on first use", fingerprint, host);
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), ClientlessError> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let mut lines: Vec<String> = self.hosts.iter()
+            .map(|e| format!("{} SHA256:{}\n", e.key(), e.value()))
+            .collect();
+        lines.sort();
+
+        // Write aside and rename so a crash never leaves a truncated file
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        let write = std::fs::write(&partial, lines.concat()).and_then(|_| std::fs::rename(&partial, path));
+        if let Err(e) = write {
+            let _ = std::fs::remove_file(&partial);
+            return Err(store_error(path, e));
+        }
+        Ok(())
+    }
+}
+
+fn store_error(path: &Path, e: std::io::Error) -> ClientlessError {
+    ClientlessError::HostKeyStore(format!("{}: {}", path.display(), e))
+}
+
+/// SSH gateway
+pub struct SshGateway {
+    known_hosts: Arc<KnownHosts>,
+    connect_timeout: Duration,
+    idle_timeout: Duration,
+}
+
+impl SshGateway {
+    pub fn new() -> Self {
+        Self {
+            known_hosts: Arc::new(KnownHosts::memory()),
+            connect_timeout: Duration::from_secs(10),
+            idle_timeout: Duration::from_secs(15 * 60),
+        }
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Keep first-use host keys in a file so they survive restarts; remove a
+    /// host's line to accept a rotated key
+    pub fn with_known_hosts_file(mut self, path: impl Into<PathBuf>) -> Result<Self, ClientlessError> {
+        self.known_hosts = Arc::new(KnownHosts::load(path.into())?);
+        Ok(self)
+    }
+
+    /// Host keys learned on first use, `host:port` → SHA-256 fingerprint
+    pub fn known_hosts(&self) -> Vec<(String, String)> {
+        self.known_hosts.hosts.iter().map(|e| (e.key().clone(), e.value().clone())).collect()
+    }
+
+    pub async fn open(
+        &self,
+        info: SshSession,
+        grant: &ClientlessGrant,
+        app: &ConnectedApp,
+        sink: Option<RecordingSink>,
+    ) -> Result<TerminalSession, ClientlessError> {
+        let credential = app.credential.as_ref().ok_or(ClientlessError::MissingCredential)?;
+        let host = format!("{}:{}", app.internal_host, app.internal_port);
+
+        let rejection = Arc::new(parking_lot::Mutex::new(None));
+        let handler = HostKeyCheck {
+            host: host.clone(),
+            expected: app.host_key_fingerprint.clone(),
+            known_hosts: self.known_hosts.clone(),
+            rejection: rejection.clone(),
+        };
+        let config = Arc::new(client::Config {
+            inactivity_timeout: Some(self.idle_timeout),
+            ..Default::default()
+        });
+
+        let connect = client::connect(config, (app.internal_host.as_str(), app.internal_port), handler);
+        let mut handle = match tokio::time::timeout(self.connect_timeout, connect).await {
+            Ok(Ok(handle)) => handle,
+            Ok(Err(e)) => {
+                let rejected = rejection.lock().take();
+                return Err(rejected.unwrap_or_else(|| ClientlessError::Upstream(e.to_string())));
+            }
+            Err(_) => return Err(ClientlessError::Upstream(format!("connect to {} timed out", host))),
+        };
+
+        let authenticated = match &credential.secret {
+            CredentialSecret::Password(password) => handle
+                .authenticate_password(&credential.username, password)
+                .await,
+            CredentialSecret::PrivateKey { pem, passphrase } => {
+                let key = russh_keys::decode_secret_key(pem, passphrase.as_deref())
+                    .map_err(|e| ClientlessError::Protocol(format!("invalid private key: {}", e)))?;
+                handle.authenticate_publickey(&credential.username, Arc::new(key)).await
+            }
+        }
+        .map_err(|e| ClientlessError::Upstream(e.to_string()))?;
+        if !authenticated {
+            return Err(ClientlessError::AuthenticationFailed);
+        }
+
+        let channel = handle.channel_open_session().await
+            .map_err(|e| ClientlessError::Upstream(e.to_string()))?;
+        channel
+            .request_pty(false, "xterm-256color", info.terminal_cols as u32, info.terminal_rows as u32, 0, 0, &[])
+            .await
+            .map_err(|e| ClientlessError::Upstream(e.to_string()))?;
+        channel.request_shell(false).await
+            .map_err(|e| ClientlessError::Upstream(e.to_string()))?;
+
+        let (input_tx, input_rx) = mpsc::channel(256);
+        let (output_tx, output_rx) = mpsc::channel(256);
+
+        let bridge = Bridge {
+            info: info.clone(),
+            app_name: app.name.clone(),
+            read_only: grant.read_only,
+            clipboard: grant.allows(AllowedAction::Clipboard),
+            expires_at: grant.expires_at,
+            sink,
+            line: String::new(),
+            escape: false,
+        };
+        tokio::spawn(async move {
+            bridge.run(channel, input_rx, output_tx).await;
+            let _ = handle.disconnect(russh::Disconnect::ByApplication, "", "en").await;
+        });
+
+        tracing::info!("SSH bridge {} to {} as {}", info.id, host, credential.username);
+        Ok(TerminalSession { info, input: input_tx, output: output_rx })
+    }
+}
+
+impl Default for SshGateway {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Bridge {
+    info: SshSession,
+    app_name: String,
+    read_only: bool,
+    clipboard: bool,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    sink: Option<RecordingSink>,
+    /// Command line being typed, for the recording
+    line: String,
+    /// Inside an escape sequence (arrow keys etc.)
+    escape: bool,
+}
+
+impl Bridge {
+    async fn run(
+        mut self,
+        mut channel: russh::Channel<client::Msg>,
+        mut input: mpsc::Receiver<TerminalInput>,
+        output: mpsc::Sender<TerminalOutput>,
+    ) {
+        let remaining = (self.expires_at - chrono::Utc::now()).to_std().unwrap_or_default();
+        let deadline = tokio::time::sleep(remaining);
+        tokio::pin!(deadline);
+        let mut status = None;
+
+        if self.read_only {
+            let _ = output.send(TerminalOutput::Notice("Read-only session: input is disabled".to_string())).await;
+        }
+
+        loop {
+            tokio::select! {
+                message = channel.wait() => match message {
+                    Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                        self.record_output(&data).await;
+                        if output.send(TerminalOutput::Data(data.to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status }) => status = Some(exit_status),
+                    Some(ChannelMsg::Eof) | Some(ChannelMsg::Close) | None => break,
+                    Some(_) => {}
+                },
+                message = input.recv() => match message {
+                    Some(TerminalInput::Data { data }) => {
+                        if self.read_only {
+                            continue;
+                        }
+                        self.record_input(&data).await;
+                        if channel.data(data.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(TerminalInput::Paste { data }) => {
+                        if self.read_only || !self.clipboard {
+                            let _ = output.send(TerminalOutput::Notice("Paste is not permitted".to_string())).await;
+                            continue;
+                        }
+                        self.record_paste(&data).await;
+                        self.record_input(&data).await;
+                        if channel.data(data.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(TerminalInput::Resize { cols, rows }) => {
+                        self.info.terminal_cols = cols;
+                        self.info.terminal_rows = rows;
+                        let _ = channel.window_change(cols as u32, rows as u32, 0, 0).await;
+                    }
+                    // Browser went away
+                    None => break,
+                },
+                _ = &mut deadline => {
+                    let _ = output.send(TerminalOutput::Notice("Access has expired".to_string())).await;
+                    break;
+                }
+            }
+        }
+
+        let _ = channel.eof().await;
+        let _ = channel.close().await;
+        let _ = output.send(TerminalOutput::Exit { status }).await;
+        tracing::info!("SSH bridge {} to {} closed (exit {:?})", self.info.id, self.app_name, status);
+    }
+
+    async fn record_output(&self, data: &[u8]) {
+        let Some(sink) = &self.sink else { return };
+        sink.recorder.record(&sink.recording_id, RecordedActivity::ScreenFrame(ScreenFrameData {
+            timestamp: chrono::Utc::now(),
+            width: self.info.terminal_cols as u32,
+            height: self.info.terminal_rows as u32,
+            frame_type: FrameType::Delta,
+            data: data.to_vec(),
+        })).await;
+    }
+
+    async fn record_paste(&self, data: &str) {
+        let Some(sink) = &self.sink else { return };
+        sink.recorder.record(&sink.recording_id, RecordedActivity::ClipboardAction(ClipboardData {
+            timestamp: chrono::Utc::now(),
+            operation: ClipboardOperation::Paste,
+            content_type: "text/plain".to_string(),
+            size_bytes: data.len() as u64,
+        })).await;
+    }
+
+    /// Record keystrokes and reassemble command lines
+    async fn record_input(&mut self, data: &str) {
+        let Some(sink) = self.sink.clone() else { return };
+
+        for ch in data.chars() {
+            sink.recorder.record_keystroke(&sink.recording_id, ch as u32, 0, &self.app_name).await;
+
+            if self.escape {
+                // CSI/SS3 sequences end on a letter or '~'
+                if ch.is_ascii_alphabetic() || ch == '~' {
+                    self.escape = false;
+                }
+                continue;
+            }
+            match ch {
+                '\x1b' => self.escape = true,
+                '\r' | '\n' => {
+                    let command = std::mem::take(&mut self.line);
+                    if !command.trim().is_empty() {
+                        sink.recorder.record_command(&sink.recording_id, command.trim(), "", None).await;
+                    }
+                }
+                '\x7f' | '\x08' => {
+                    self.line.pop();
+                }
+                // Ctrl-C / Ctrl-U abandon the line
+                '\x03' | '\x15' => self.line.clear(),
+                c if !c.is_control() && self.line.len() < MAX_COMMAND_LEN => self.line.push(c),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::testing::RequestFixture;
+    use crate::recording::{EnhancedSessionRecorder, RecordingType};
+    use crate::{Session, SessionStatus, TrustLevel};
+    use russh::client::Handler;
+
+    fn host_key() -> russh_keys::key::PublicKey {
+        russh_keys::key::KeyPair::generate_ed25519().unwrap().clone_public_key().unwrap()
+    }
+
+    fn check(expected: Option<String>, known_hosts: &Arc<KnownHosts>) -> HostKeyCheck {
+        HostKeyCheck {
+            host: "10.0.0.5:22".to_string(),
+            expected,
+            known_hosts: known_hosts.clone(),
+            rejection: Arc::new(parking_lot::Mutex::new(None)),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", uuid::Uuid::new_v4(), name))
+    }
+
+    #[tokio::test]
+    async fn test_pinned_host_key_mismatch() {
+        let known_hosts = Arc::new(KnownHosts::memory());
+        let key = host_key();
+
+        let pinned = check(Some(format!("SHA256:{}", key.fingerprint())), &known_hosts);
+        let (_, accepted) = pinned.check_server_key(&key).await.unwrap();
+        assert!(accepted);
+
+        let other = check(Some(format!("SHA256:{}", host_key().fingerprint())), &known_hosts);
+        let rejection = other.rejection.clone();
+        let (_, accepted) = other.check_server_key(&key).await.unwrap();
+        assert!(!accepted);
+        assert!(matches!(rejection.lock().take(), Some(ClientlessError::HostKeyMismatch)));
+
+        // A pinned application never touches the first-use store
+        assert!(known_hosts.hosts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_first_use_key_is_pinned() {
+        let known_hosts = Arc::new(KnownHosts::memory());
+        let key = host_key();
+
+        let (_, accepted) = check(None, &known_hosts).check_server_key(&key).await.unwrap();
+        assert!(accepted);
+        let (_, accepted) = check(None, &known_hosts).check_server_key(&key).await.unwrap();
+        assert!(accepted);
+
+        let changed = check(None, &known_hosts);
+        let rejection = changed.rejection.clone();
+        let (_, accepted) = changed.check_server_key(&host_key()).await.unwrap();
+        assert!(!accepted);
+        assert!(matches!(rejection.lock().take(), Some(ClientlessError::HostKeyMismatch)));
+        assert_eq!(known_hosts.hosts.get("10.0.0.5:22").unwrap().as_str(), key.fingerprint());
+    }
+
+    #[test]
+    fn test_known_hosts_file_survives_restart() {
+        let path = temp_path("known_hosts");
+        let gateway = SshGateway::new().with_known_hosts_file(&path).unwrap();
+        assert!(gateway.known_hosts().is_empty());
+
+        gateway.known_hosts.check("db.internal:22", "first").unwrap();
+        gateway.known_hosts.check("app.internal:2222", "second").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "app.internal:2222 SHA256:second\ndb.internal:22 SHA256:first\n",
+        );
+
+        let restarted = SshGateway::new().with_known_hosts_file(&path).unwrap();
+        assert!(restarted.known_hosts.check("db.internal:22", "first").is_ok());
+        assert!(matches!(
+            restarted.known_hosts.check("db.internal:22", "changed"),
+            Err(ClientlessError::HostKeyMismatch)
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_known_hosts_file_errors() {
+        let path = temp_path("known_hosts");
+        std::fs::write(&path, "# pinned by hand\n\ndb.internal:22 SHA256:abc\nbroken\n").unwrap();
+        let error = SshGateway::new().with_known_hosts_file(&path).err().unwrap();
+        assert!(matches!(error, ClientlessError::HostKeyStore(message) if message.ends_with(":4: expected '<host:port> SHA256:<fingerprint>'")));
+        let _ = std::fs::remove_file(&path);
+
+        // A pin that can't be written is refused, not trusted for this run
+        let unwritable = KnownHosts::load(temp_path("missing-dir").join("known_hosts")).unwrap();
+        assert!(matches!(unwritable.check("db.internal:22", "abc"), Err(ClientlessError::HostKeyStore(_))));
+        assert!(unwritable.hosts.is_empty());
+    }
+
+    async fn recorded_bridge(clipboard: bool) -> (Bridge, Arc<EnhancedSessionRecorder>, String) {
+        let request = RequestFixture::default().build().unwrap();
+        let now = chrono::Utc::now();
+        let session = Session {
+            id: "session-1".to_string(),
+            identity: request.identity,
+            device: request.device,
+            created_at: now,
+            last_activity: now,
+            expires_at: now + chrono::Duration::hours(1),
+            trust_level: TrustLevel::High,
+            risk_score: 0.0,
+            active_resources: Default::default(),
+            status: SessionStatus::Active,
+        };
+        let recorder = Arc::new(EnhancedSessionRecorder::new());
+        let recording_id = recorder.start(&session, RecordingType::Full).await;
+
+        let bridge = Bridge {
+            info: SshSession {
+                id: "ssh-1".to_string(),
+                session_id: session.id.clone(),
+                app_id: "app-1".to_string(),
+                terminal_cols: 80,
+                terminal_rows: 24,
+                recording_enabled: true,
+            },
+            app_name: "bastion".to_string(),
+            read_only: false,
+            clipboard,
+            expires_at: session.expires_at,
+            sink: Some(RecordingSink { recorder: recorder.clone(), recording_id: recording_id.clone() }),
+            line: String::new(),
+            escape: false,
+        };
+        (bridge, recorder, recording_id)
+    }
+
+    #[tokio::test]
+    async fn test_recording_reassembles_commands() {
+        let (mut bridge, recorder, recording_id) = recorded_bridge(true).await;
+
+        // Backspace edits, arrow keys are skipped, Ctrl-C abandons the line
+        bridge.record_input("lss\x7f -la\r").await;
+        bridge.record_input("\x1b[Auname").await;
+        bridge.record_input(" -a\n").await;
+        bridge.record_input("rm -rf /\x03\r").await;
+        bridge.record_paste("whoami\r").await;
+        bridge.record_input("whoami\r").await;
+        bridge.record_output(b"root\r\n").await;
+
+        let replay = recorder.get_replay(&recording_id).await.unwrap();
+        let commands: Vec<&str> = replay.activities.iter()
+            .filter_map(|a| match a {
+                RecordedActivity::Command(c) => Some(c.command.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(commands, vec!["ls -la", "uname -a", "whoami"]);
+
+        let keystrokes = replay.activities.iter().filter(|a| matches!(a, RecordedActivity::Keystroke(_))).count();
+        assert_eq!(keystrokes, "lss\x7f -la\r\x1b[Auname -a\nrm -rf /\x03\rwhoami\r".chars().count());
+        assert!(replay.activities.iter().any(|a| matches!(a, RecordedActivity::ClipboardAction(c) if c.size_bytes == 7)));
+        assert_eq!(replay.recording.metadata.commands_executed, 3);
+        assert_eq!(replay.recording.size_bytes, 6);
+    }
+
+    #[tokio::test]
+    async fn test_overlong_command_is_truncated() {
+        let (mut bridge, recorder, recording_id) = recorded_bridge(false).await;
+        bridge.record_input(&"x".repeat(MAX_COMMAND_LEN + 10)).await;
+        bridge.record_input("\r").await;
+
+        let replay = recorder.get_replay(&recording_id).await.unwrap();
+        let command = replay.activities.iter()
+            .find_map(|a| match a {
+                RecordedActivity::Command(c) => Some(c.command.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(command.len(), MAX_COMMAND_LEN);
+    }
+}
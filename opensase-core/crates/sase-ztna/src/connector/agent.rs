@@ -0,0 +1,267 @@
+//! Connector Agent
+//!
+//! Runs next to private applications. Dials out to the PoP (no inbound
+//! ports), registers the applications it can reach, heartbeats, and relays
+//! each stream the PoP opens to the matching local target.
+
+use super::mux::{relay, IncomingStream, Mux, MuxConfig, MuxEvent};
+use super::protocol::{
+    read_frame, send_frame, Frame, FrameType, Heartbeat, Register, RegisterAck, CONTROL_STREAM,
+};
+use super::ConnectorError;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Heartbeats without an ack before the link is considered dead
+const MAX_UNACKED_HEARTBEATS: u64 = 3;
+
+#[derive(Debug, Clone)]
+pub struct AppTarget {
+    pub application_id: String,
+    /// Local `host:port` the application listens on
+    pub target: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    /// Must match the CN of the agent's client certificate
+    pub connector_id: String,
+    pub applications: Vec<AppTarget>,
+    pub max_streams: u32,
+    /// Wait for a local application to accept a connection
+    pub connect_timeout: Duration,
+    pub handshake_timeout: Duration,
+    pub reconnect_min: Duration,
+    pub reconnect_max: Duration,
+    pub mux: MuxConfig,
+}
+
+impl AgentConfig {
+    pub fn new(connector_id: &str) -> Self {
+        Self {
+            connector_id: connector_id.to_string(),
+            applications: Vec::new(),
+            max_streams: 1024,
+            connect_timeout: Duration::from_secs(5),
+            handshake_timeout: Duration::from_secs(10),
+            reconnect_min: Duration::from_secs(1),
+            reconnect_max: Duration::from_secs(60),
+            mux: MuxConfig::default(),
+        }
+    }
+
+    pub fn with_application(mut self, application_id: &str, target: &str) -> Self {
+        self.applications.push(AppTarget {
+            application_id: application_id.to_string(),
+            target: target.to_string(),
+        });
+        self
+    }
+}
+
+pub struct ConnectorAgent {
+    config: AgentConfig,
+    targets: Arc<HashMap<String, String>>,
+    /// Applications whose target refused or timed out on the last attempt
+    unhealthy: Arc<parking_lot::Mutex<HashSet<String>>>,
+}
+
+impl ConnectorAgent {
+    pub fn new(config: AgentConfig) -> Self {
+        let targets = config.applications.iter()
+            .map(|a| (a.application_id.clone(), a.target.clone()))
+            .collect();
+        Self {
+            config,
+            targets: Arc::new(targets),
+            unhealthy: Arc::new(parking_lot::Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
+    /// Register over an established transport and serve streams until the
+    /// link closes. Returns `Ok` when the PoP asked us to go away.
+    pub async fn run_session<T>(&self, transport: T) -> Result<(), ConnectorError>
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (mut reader, mut writer) = tokio::io::split(transport);
+
+        let register = Register {
+            connector_id: self.config.connector_id.clone(),
+            agent_version: env!("CARGO_PKG_VERSION").to_string(),
+            applications: self.config.applications.iter().map(|a| a.application_id.clone()).collect(),
+            max_streams: self.config.max_streams,
+        };
+        send_frame(&mut writer, &Frame::control(FrameType::Register, CONTROL_STREAM, &register)).await?;
+
+        let frame = tokio::time::timeout(self.config.handshake_timeout, read_frame(&mut reader))
+            .await
+            .map_err(|_| ConnectorError::Timeout)??
+            .ok_or(ConnectorError::ConnectionClosed)?;
+        if frame.frame_type != FrameType::RegisterAck {
+            return Err(ConnectorError::Protocol(format!("expected RegisterAck, got {:?}", frame.frame_type)));
+        }
+        let ack: RegisterAck = frame.decode()?;
+        if !ack.accepted {
+            return Err(ConnectorError::Registration(ack.reason.unwrap_or_else(|| "rejected".to_string())));
+        }
+        if !ack.rejected_applications.is_empty() {
+            tracing::warn!("PoP refused to publish {:?}", ack.rejected_applications);
+        }
+        tracing::info!(
+            "Connector {} registered (session {})",
+            self.config.connector_id, ack.session_id
+        );
+
+        let (mux, mut events) = Mux::start(reader, writer, self.config.mux.clone());
+        let result = self.serve(&mux, &mut events, Duration::from_secs(ack.heartbeat_interval_secs.max(1))).await;
+        mux.shutdown();
+        result
+    }
+
+    async fn serve(
+        &self,
+        mux: &Mux,
+        events: &mut tokio::sync::mpsc::UnboundedReceiver<MuxEvent>,
+        heartbeat_interval: Duration,
+    ) -> Result<(), ConnectorError> {
+        let mut ticker = tokio::time::interval(heartbeat_interval);
+        let mut sequence = 0u64;
+        let mut acked = 0u64;
+        let mut sent_at = Instant::now();
+        let mut rtt_ms = None;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if sequence - acked >= MAX_UNACKED_HEARTBEATS {
+                        return Err(ConnectorError::Timeout);
+                    }
+                    sequence += 1;
+                    sent_at = Instant::now();
+
+                    let mut unhealthy_applications: Vec<String> = self.unhealthy.lock().iter().cloned().collect();
+                    unhealthy_applications.sort();
+                    let heartbeat = Heartbeat {
+                        sequence,
+                        active_streams: mux.active_streams() as u32,
+                        unhealthy_applications,
+                        rtt_ms,
+                    };
+                    mux.send(Frame::control(FrameType::Heartbeat, CONTROL_STREAM, &heartbeat)).await?;
+                }
+                event = events.recv() => match event {
+                    Some(MuxEvent::HeartbeatAck(ack)) => {
+                        if ack.sequence == sequence {
+                            rtt_ms = Some(sent_at.elapsed().as_millis() as u64);
+                        }
+                        acked = acked.max(ack.sequence);
+                    }
+                    Some(MuxEvent::Incoming(incoming)) => {
+                        tokio::spawn(handle_stream(
+                            incoming,
+                            self.targets.clone(),
+                            self.unhealthy.clone(),
+                            self.config.connect_timeout,
+                        ));
+                    }
+                    Some(MuxEvent::GoAway(reason)) => {
+                        tracing::info!("PoP is draining ({}), reconnecting", reason);
+                        return Ok(());
+                    }
+                    Some(MuxEvent::Heartbeat(_)) => {}
+                    Some(MuxEvent::Closed) | None => return Err(ConnectorError::ConnectionClosed),
+                },
+            }
+        }
+    }
+
+    /// Keep a session to the PoP over mTLS, reconnecting with backoff.
+    /// Only returns if `server_name` is not a valid TLS name.
+    pub async fn run_tls(
+        &self,
+        pop_addr: &str,
+        server_name: &str,
+        tls: tokio_rustls::TlsConnector,
+    ) -> Result<(), ConnectorError> {
+        let server_name = tokio_rustls::rustls::ServerName::try_from(server_name)
+            .map_err(|e| ConnectorError::Protocol(format!("invalid server name: {}", e)))?;
+        let mut backoff = self.config.reconnect_min;
+
+        loop {
+            let started = Instant::now();
+            let result = async {
+                let tcp = tokio::net::TcpStream::connect(pop_addr).await?;
+                tcp.set_nodelay(true)?;
+                let stream = tls.connect(server_name.clone(), tcp).await?;
+                self.run_session(stream).await
+            }.await;
+
+            match result {
+                Ok(()) => tracing::info!("Session to {} ended", pop_addr),
+                Err(e) => tracing::warn!("Session to {} failed: {}", pop_addr, e),
+            }
+
+            // A session that lived a while resets the backoff
+            if started.elapsed() >= self.config.reconnect_max {
+                backoff = self.config.reconnect_min;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.config.reconnect_max);
+        }
+    }
+}
+
+/// Connect a stream to its local application and relay until done
+async fn handle_stream(
+    incoming: IncomingStream,
+    targets: Arc<HashMap<String, String>>,
+    unhealthy: Arc<parking_lot::Mutex<HashSet<String>>>,
+    connect_timeout: Duration,
+) {
+    let application_id = incoming.request.application_id.clone();
+    let Some(target) = targets.get(&application_id) else {
+        incoming.reject("application not published by this connector").await;
+        return;
+    };
+
+    let socket = match tokio::time::timeout(connect_timeout, tokio::net::TcpStream::connect(target)).await {
+        Ok(Ok(socket)) => socket,
+        Ok(Err(e)) => {
+            unhealthy.lock().insert(application_id.clone());
+            incoming.reject(&format!("{} unreachable: {}", application_id, e)).await;
+            return;
+        }
+        Err(_) => {
+            unhealthy.lock().insert(application_id.clone());
+            incoming.reject(&format!("{} connect timed out", application_id)).await;
+            return;
+        }
+    };
+    unhealthy.lock().remove(&application_id);
+    let _ = socket.set_nodelay(true);
+
+    let tunnel_id = incoming.request.tunnel_id.clone();
+    let user_id = incoming.request.user_id.clone();
+    let stream = match incoming.accept().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            tracing::debug!("Tunnel {} lost before accept: {}", tunnel_id, e);
+            return;
+        }
+    };
+
+    match relay(stream, socket).await {
+        Ok((up, down)) => tracing::debug!(
+            "Tunnel {} for {} to {} closed ({} bytes up, {} bytes down)",
+            tunnel_id, user_id, application_id, up, down
+        ),
+        Err(e) => tracing::debug!("Tunnel {} to {} aborted: {}", tunnel_id, application_id, e),
+    }
+}
@@ -0,0 +1,614 @@
+//! Connector Hub (PoP side)
+//!
+//! Terminates connector links, tracks their health from heartbeats and
+//! picks a connector for each authorised flow. Connectors authenticate with
+//! a client certificate whose common name must equal the connector id they
+//! register with.
+
+use super::mux::{Mux, MuxConfig, MuxEvent, MuxStream};
+use super::protocol::{
+    read_frame, send_frame, Frame, FrameType, HeartbeatAck, OpenStream, Register, RegisterAck,
+    CONTROL_STREAM,
+};
+use super::{ConnectorError, ConnectorHealth};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+#[derive(Debug, Clone)]
+pub struct HubConfig {
+    /// Interval connectors are told to heartbeat at
+    pub heartbeat_interval: Duration,
+    /// Missed heartbeats before a connector is degraded
+    pub degraded_after_missed: u32,
+    /// Missed heartbeats before a connector is dropped
+    pub dead_after_missed: u32,
+    pub handshake_timeout: Duration,
+    /// Wait for a connector to reach the application
+    pub open_timeout: Duration,
+    /// Only accept applications explicitly allowed per connector
+    pub require_publish_acl: bool,
+    pub mux: MuxConfig,
+}
+
+impl Default for HubConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(15),
+            degraded_after_missed: 2,
+            dead_after_missed: 4,
+            handshake_timeout: Duration::from_secs(10),
+            open_timeout: Duration::from_secs(10),
+            require_publish_acl: false,
+            mux: MuxConfig::default(),
+        }
+    }
+}
+
+/// A registered connector with an open link
+pub struct LiveConnector {
+    pub connector_id: String,
+    pub session_id: String,
+    pub peer: Option<SocketAddr>,
+    pub agent_version: String,
+    /// Applications accepted at registration
+    pub applications: Vec<String>,
+    pub max_streams: u32,
+    pub connected_at: DateTime<Utc>,
+    mux: Mux,
+    link: parking_lot::Mutex<LinkState>,
+}
+
+struct LinkState {
+    last_heartbeat: Instant,
+    last_heartbeat_at: DateTime<Utc>,
+    rtt_ms: Option<u64>,
+    health: ConnectorHealth,
+    unhealthy_applications: HashSet<String>,
+}
+
+impl LiveConnector {
+    pub fn health(&self) -> ConnectorHealth {
+        self.link.lock().health
+    }
+
+    pub fn active_streams(&self) -> usize {
+        self.mux.active_streams()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.mux.is_draining()
+    }
+
+    /// Can this connector take a new flow for `application_id`
+    fn can_serve(&self, application_id: &str) -> bool {
+        if self.mux.is_closed() || self.mux.is_draining() {
+            return false;
+        }
+        if !self.applications.iter().any(|a| a == application_id) {
+            return false;
+        }
+        if self.active_streams() >= self.max_streams as usize {
+            return false;
+        }
+        let link = self.link.lock();
+        link.health != ConnectorHealth::Unhealthy && !link.unhealthy_applications.contains(application_id)
+    }
+
+    /// Ordering key: health, then load, then latency (lower is better)
+    fn rank(&self) -> (u8, u64, u64) {
+        let link = self.link.lock();
+        let health = match link.health {
+            ConnectorHealth::Healthy => 0,
+            ConnectorHealth::Unknown => 1,
+            ConnectorHealth::Degraded => 2,
+            ConnectorHealth::Unhealthy => 3,
+        };
+        // Load in tenths so near-equal connectors share traffic
+        let load = (self.active_streams() as u64 * 10) / self.max_streams.max(1) as u64;
+        // Latency in 10 ms buckets; unknown sorts after measured
+        let rtt = link.rtt_ms.map(|ms| ms / 10).unwrap_or(u64::MAX);
+        (health, load, rtt)
+    }
+
+    fn on_heartbeat(&self, unhealthy_applications: Vec<String>, rtt_ms: Option<u64>) {
+        let mut link = self.link.lock();
+        link.last_heartbeat = Instant::now();
+        link.last_heartbeat_at = Utc::now();
+        if rtt_ms.is_some() {
+            link.rtt_ms = rtt_ms;
+        }
+        link.unhealthy_applications = unhealthy_applications.into_iter().collect();
+        link.health = if !self.applications.is_empty()
+            && self.applications.iter().all(|a| link.unhealthy_applications.contains(a))
+        {
+            ConnectorHealth::Degraded
+        } else {
+            ConnectorHealth::Healthy
+        };
+    }
+
+    fn status(&self) -> ConnectorStatus {
+        let link = self.link.lock();
+        let mut unhealthy_applications: Vec<String> = link.unhealthy_applications.iter().cloned().collect();
+        unhealthy_applications.sort();
+        ConnectorStatus {
+            connector_id: self.connector_id.clone(),
+            session_id: self.session_id.clone(),
+            peer: self.peer,
+            agent_version: self.agent_version.clone(),
+            applications: self.applications.clone(),
+            unhealthy_applications,
+            health: format!("{:?}", link.health),
+            active_streams: self.mux.active_streams(),
+            max_streams: self.max_streams,
+            rtt_ms: link.rtt_ms,
+            draining: self.mux.is_draining(),
+            connected_at: self.connected_at,
+            last_heartbeat: link.last_heartbeat_at,
+        }
+    }
+}
+
+/// Point-in-time view of a live connector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectorStatus {
+    pub connector_id: String,
+    pub session_id: String,
+    pub peer: Option<SocketAddr>,
+    pub agent_version: String,
+    pub applications: Vec<String>,
+    pub unhealthy_applications: Vec<String>,
+    pub health: String,
+    pub active_streams: usize,
+    pub max_streams: u32,
+    pub rtt_ms: Option<u64>,
+    pub draining: bool,
+    pub connected_at: DateTime<Utc>,
+    pub last_heartbeat: DateTime<Utc>,
+}
+
+/// Registry of live connector links
+pub struct ConnectorHub {
+    config: HubConfig,
+    connectors: dashmap::DashMap<String, Arc<LiveConnector>>,
+    /// Connector id → applications it may publish
+    publish_acl: dashmap::DashMap<String, HashSet<String>>,
+    round_robin: AtomicUsize,
+}
+
+impl ConnectorHub {
+    pub fn new(config: HubConfig) -> Self {
+        Self {
+            config,
+            connectors: dashmap::DashMap::new(),
+            publish_acl: dashmap::DashMap::new(),
+            round_robin: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &HubConfig {
+        &self.config
+    }
+
+    /// Allow a connector to publish an application
+    pub fn allow_publish(&self, connector_id: &str, application_id: &str) {
+        self.publish_acl.entry(connector_id.to_string())
+            .or_default()
+            .insert(application_id.to_string());
+    }
+
+    pub fn revoke_publish(&self, connector_id: &str, application_id: &str) {
+        if let Some(mut apps) = self.publish_acl.get_mut(connector_id) {
+            apps.remove(application_id);
+        }
+    }
+
+    fn may_publish(&self, connector_id: &str, application_id: &str) -> bool {
+        !self.config.require_publish_acl
+            || self.publish_acl.get(connector_id)
+                .map(|apps| apps.contains(application_id))
+                .unwrap_or(false)
+    }
+
+    /// Run the registration handshake on an authenticated transport and
+    /// take the link into service. `peer_identity` is the identity proven
+    /// by the transport (certificate CN).
+    pub async fn accept<T>(
+        self: &Arc<Self>,
+        transport: T,
+        peer_identity: &str,
+        peer: Option<SocketAddr>,
+    ) -> Result<Arc<LiveConnector>, ConnectorError>
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (mut reader, mut writer) = tokio::io::split(transport);
+
+        let frame = tokio::time::timeout(self.config.handshake_timeout, read_frame(&mut reader))
+            .await
+            .map_err(|_| ConnectorError::Timeout)??
+            .ok_or(ConnectorError::ConnectionClosed)?;
+        if frame.frame_type != FrameType::Register {
+            return Err(ConnectorError::Protocol(format!("expected Register, got {:?}", frame.frame_type)));
+        }
+        let register: Register = frame.decode()?;
+
+        let refuse = |reason: String| RegisterAck {
+            accepted: false,
+            session_id: String::new(),
+            heartbeat_interval_secs: 0,
+            rejected_applications: Vec::new(),
+            reason: Some(reason),
+        };
+
+        if register.connector_id != peer_identity {
+            let reason = format!(
+                "connector id {} does not match certificate identity {}",
+                register.connector_id, peer_identity
+            );
+            let _ = send_frame(&mut writer, &Frame::control(FrameType::RegisterAck, CONTROL_STREAM, &refuse(reason.clone()))).await;
+            return Err(ConnectorError::Registration(reason));
+        }
+
+        let (applications, rejected_applications): (Vec<String>, Vec<String>) = register.applications
+            .into_iter()
+            .partition(|app| self.may_publish(&register.connector_id, app));
+        if applications.is_empty() {
+            let reason = "no publishable applications".to_string();
+            let _ = send_frame(&mut writer, &Frame::control(FrameType::RegisterAck, CONTROL_STREAM, &refuse(reason.clone()))).await;
+            return Err(ConnectorError::Registration(reason));
+        }
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let ack = RegisterAck {
+            accepted: true,
+            session_id: session_id.clone(),
+            heartbeat_interval_secs: self.config.heartbeat_interval.as_secs().max(1),
+            rejected_applications: rejected_applications.clone(),
+            reason: None,
+        };
+        send_frame(&mut writer, &Frame::control(FrameType::RegisterAck, CONTROL_STREAM, &ack)).await?;
+
+        let (mux, events) = Mux::start(reader, writer, self.config.mux.clone());
+        let connector = Arc::new(LiveConnector {
+            connector_id: register.connector_id.clone(),
+            session_id,
+            peer,
+            agent_version: register.agent_version,
+            applications,
+            max_streams: register.max_streams.max(1),
+            connected_at: Utc::now(),
+            mux,
+            link: parking_lot::Mutex::new(LinkState {
+                last_heartbeat: Instant::now(),
+                last_heartbeat_at: Utc::now(),
+                rtt_ms: None,
+                health: ConnectorHealth::Unknown,
+                unhealthy_applications: HashSet::new(),
+            }),
+        });
+
+        // A reconnect replaces the previous link for the same connector
+        if let Some(previous) = self.connectors.insert(connector.connector_id.clone(), connector.clone()) {
+            tracing::info!("Connector {} reconnected, closing previous link", previous.connector_id);
+            previous.mux.shutdown();
+        }
+
+        tracing::info!(
+            "Connector {} registered from {:?} publishing {:?} (rejected {:?})",
+            connector.connector_id, peer, connector.applications, rejected_applications
+        );
+
+        tokio::spawn(Self::drive(Arc::downgrade(self), connector.clone(), events));
+        Ok(connector)
+    }
+
+    /// Handle link events until the link closes
+    async fn drive(
+        hub: std::sync::Weak<Self>,
+        connector: Arc<LiveConnector>,
+        mut events: tokio::sync::mpsc::UnboundedReceiver<MuxEvent>,
+    ) {
+        while let Some(event) = events.recv().await {
+            match event {
+                MuxEvent::Heartbeat(heartbeat) => {
+                    connector.on_heartbeat(heartbeat.unhealthy_applications, heartbeat.rtt_ms);
+                    let ack = HeartbeatAck { sequence: heartbeat.sequence };
+                    let _ = connector.mux.send(Frame::control(FrameType::HeartbeatAck, CONTROL_STREAM, &ack)).await;
+                }
+                MuxEvent::GoAway(reason) => {
+                    tracing::info!("Connector {} draining: {}", connector.connector_id, reason);
+                }
+                MuxEvent::Incoming(incoming) => {
+                    incoming.reject("connectors may not open streams").await;
+                }
+                MuxEvent::HeartbeatAck(_) => {}
+                MuxEvent::Closed => break,
+            }
+        }
+
+        tracing::info!("Connector {} link closed", connector.connector_id);
+        if let Some(hub) = hub.upgrade() {
+            hub.connectors.remove_if(&connector.connector_id, |_, c| c.session_id == connector.session_id);
+        }
+    }
+
+    /// Accept mTLS connector links until the listener fails
+    pub async fn serve_tls(
+        self: Arc<Self>,
+        listener: tokio::net::TcpListener,
+        acceptor: tokio_rustls::TlsAcceptor,
+    ) -> std::io::Result<()> {
+        loop {
+            let (tcp, peer) = listener.accept().await?;
+            let hub = self.clone();
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                let tls = match tokio::time::timeout(hub.config.handshake_timeout, acceptor.accept(tcp)).await {
+                    Ok(Ok(tls)) => tls,
+                    Ok(Err(e)) => {
+                        tracing::warn!("Connector TLS handshake from {} failed: {}", peer, e);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::warn!("Connector TLS handshake from {} timed out", peer);
+                        return;
+                    }
+                };
+
+                let identity = tls.get_ref().1.peer_certificates()
+                    .and_then(|certs| certs.first())
+                    .and_then(|cert| certificate_common_name(&cert.0));
+                let Some(identity) = identity else {
+                    tracing::warn!("Connector {} presented no usable client certificate", peer);
+                    return;
+                };
+
+                if let Err(e) = hub.accept(tls, &identity, Some(peer)).await {
+                    tracing::warn!("Connector registration from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// Connectors able to serve an application, best first. Connectors
+    /// that rank equally are rotated so they share new flows.
+    pub fn candidates(&self, application_id: &str) -> Vec<Arc<LiveConnector>> {
+        let mut ranked: Vec<((u8, u64, u64), Arc<LiveConnector>)> = self.connectors.iter()
+            .filter(|c| c.can_serve(application_id))
+            .map(|c| (c.rank(), c.clone()))
+            .collect();
+        ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.connector_id.cmp(&b.1.connector_id)));
+
+        if let Some(best) = ranked.first().map(|(rank, _)| *rank) {
+            let tied = ranked.iter().take_while(|(rank, _)| *rank == best).count();
+            let offset = self.round_robin.fetch_add(1, Ordering::Relaxed) % tied;
+            ranked[..tied].rotate_left(offset);
+        }
+
+        ranked.into_iter().map(|(_, c)| c).collect()
+    }
+
+    /// Open a stream to an application, failing over across connectors
+    pub async fn open(
+        &self,
+        application_id: &str,
+        tunnel_id: &str,
+        user_id: &str,
+    ) -> Result<(Arc<LiveConnector>, MuxStream), ConnectorError> {
+        let request = OpenStream {
+            application_id: application_id.to_string(),
+            tunnel_id: tunnel_id.to_string(),
+            user_id: user_id.to_string(),
+        };
+
+        let mut last_error = ConnectorError::NoConnectorAvailable;
+        for connector in self.candidates(application_id) {
+            match connector.mux.open(&request, self.config.open_timeout).await {
+                Ok(stream) => {
+                    tracing::debug!(
+                        "Tunnel {} to {} via connector {} (stream {})",
+                        tunnel_id, application_id, connector.connector_id, stream.id()
+                    );
+                    return Ok((connector, stream));
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Connector {} could not open {} for tunnel {}: {}",
+                        connector.connector_id, application_id, tunnel_id, e
+                    );
+                    // Until the next heartbeat says otherwise
+                    if matches!(e, ConnectorError::StreamRejected(_)) {
+                        connector.link.lock().unhealthy_applications.insert(application_id.to_string());
+                    }
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Age connectors by missed heartbeats; drop the dead ones
+    pub fn check_health(&self) {
+        let interval = self.config.heartbeat_interval.as_secs_f64().max(0.001);
+        let mut dead = Vec::new();
+
+        for connector in self.connectors.iter() {
+            let mut link = connector.link.lock();
+            let missed = (link.last_heartbeat.elapsed().as_secs_f64() / interval) as u32;
+            if missed >= self.config.dead_after_missed {
+                link.health = ConnectorHealth::Unhealthy;
+                dead.push(connector.clone());
+            } else if missed >= self.config.degraded_after_missed {
+                link.health = ConnectorHealth::Degraded;
+            }
+        }
+
+        for connector in dead {
+            tracing::warn!("Connector {} missed heartbeats, dropping link", connector.connector_id);
+            connector.mux.shutdown();
+            self.connectors.remove_if(&connector.connector_id, |_, c| c.session_id == connector.session_id);
+        }
+    }
+
+    /// Run `check_health` periodically
+    pub fn spawn_health_monitor(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let hub = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match hub.upgrade() {
+                    Some(hub) => hub.check_health(),
+                    None => break,
+                }
+            }
+        })
+    }
+
+    /// Ask a connector to drain: no new flows, existing ones finish
+    pub async fn drain(&self, connector_id: &str) -> bool {
+        let Some(connector) = self.connector(connector_id) else {
+            return false;
+        };
+        connector.mux.go_away("drain requested").await;
+        true
+    }
+
+    /// Drop a connector link and all its flows
+    pub fn disconnect(&self, connector_id: &str) -> bool {
+        match self.connectors.remove(connector_id) {
+            Some((_, connector)) => {
+                connector.mux.shutdown();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn connector(&self, connector_id: &str) -> Option<Arc<LiveConnector>> {
+        self.connectors.get(connector_id).map(|c| c.clone())
+    }
+
+    pub fn snapshot(&self) -> Vec<ConnectorStatus> {
+        let mut status: Vec<ConnectorStatus> = self.connectors.iter().map(|c| c.status()).collect();
+        status.sort_by(|a, b| a.connector_id.cmp(&b.connector_id));
+        status
+    }
+
+    pub fn len(&self) -> usize {
+        self.connectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connectors.is_empty()
+    }
+
+    pub fn active_streams(&self) -> usize {
+        self.connectors.iter().map(|c| c.active_streams()).sum()
+    }
+}
+
+impl Default for ConnectorHub {
+    fn default() -> Self {
+        Self::new(HubConfig::default())
+    }
+}
+
+fn certificate_common_name(der: &[u8]) -> Option<String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    let cn = cert.subject().iter_common_name().next()?.as_str().ok()?.to_string();
+    Some(cn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::agent::{AgentConfig, ConnectorAgent};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Local application that echoes one request
+    async fn echo_app() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 64];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let _ = socket.write_all(&buf[..n]).await;
+                });
+            }
+        });
+        address
+    }
+
+    /// Address nothing listens on
+    async fn closed_port() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    async fn connect(hub: &Arc<ConnectorHub>, agent: ConnectorAgent, identity: &str) -> Result<Arc<LiveConnector>, ConnectorError> {
+        let (pop_side, agent_side) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move { agent.run_session(agent_side).await });
+        hub.accept(pop_side, identity, None).await
+    }
+
+    #[tokio::test]
+    async fn test_registration_checks_identity_and_acl() {
+        let hub = Arc::new(ConnectorHub::new(HubConfig { require_publish_acl: true, ..Default::default() }));
+        hub.allow_publish("conn-a", "wiki");
+
+        let agent = || ConnectorAgent::new(
+            AgentConfig::new("conn-a")
+                .with_application("wiki", "127.0.0.1:1")
+                .with_application("payroll", "127.0.0.1:2"),
+        );
+
+        let err = connect(&hub, agent(), "conn-b").await.err().unwrap();
+        assert!(matches!(err, ConnectorError::Registration(_)), "{:?}", err);
+
+        let connector = connect(&hub, agent(), "conn-a").await.unwrap();
+        assert_eq!(connector.applications, vec!["wiki"]);
+        assert_eq!(hub.len(), 1);
+        assert!(hub.candidates("payroll").is_empty());
+
+        hub.revoke_publish("conn-a", "wiki");
+        let err = connect(&hub, agent(), "conn-a").await.err().unwrap();
+        assert!(matches!(err, ConnectorError::Registration(ref r) if r.contains("no publishable")), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_open_relays_and_fails_over() {
+        let hub = Arc::new(ConnectorHub::new(HubConfig::default()));
+        let app = echo_app().await;
+
+        connect(&hub, ConnectorAgent::new(AgentConfig::new("broken").with_application("wiki", &closed_port().await)), "broken")
+            .await.unwrap();
+        connect(&hub, ConnectorAgent::new(AgentConfig::new("working").with_application("wiki", &app)), "working")
+            .await.unwrap();
+        assert_eq!(hub.candidates("wiki").len(), 2);
+
+        // Whichever connector is tried first, the flow lands on the working one
+        let (connector, mut stream) = hub.open("wiki", "t-1", "alice").await.unwrap();
+        assert_eq!(connector.connector_id, "working");
+        stream.send(b"ping").await.unwrap();
+        assert_eq!(stream.recv().await.unwrap(), b"ping");
+
+        // The broken connector is skipped once it has refused the app
+        let _ = hub.open("wiki", "t-2", "alice").await.unwrap();
+        let ids: Vec<String> = hub.candidates("wiki").iter().map(|c| c.connector_id.clone()).collect();
+        assert_eq!(ids, vec!["working"]);
+
+        assert!(hub.disconnect("working"));
+        assert!(matches!(hub.open("wiki", "t-3", "alice").await, Err(ConnectorError::NoConnectorAvailable)));
+    }
+}
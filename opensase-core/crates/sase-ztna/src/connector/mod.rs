@@ -1,10 +1,27 @@
 //! Application Connector
 //!
-//! Secure tunnels to protected applications.
+//! Secure tunnels to protected applications. Private applications are
+//! reached through connector agents that dial out to the PoP and carry
+//! user flows as multiplexed streams:
+//!
+//! - [`protocol`]: wire framing and control messages
+//! - [`mux`]: stream multiplexing with per-stream flow control
+//! - [`hub`]: PoP side: registration, health, load balancing
+//! - [`agent`]: connector side: registration, heartbeat, relaying
+
+pub mod protocol;
+pub mod mux;
+pub mod hub;
+pub mod agent;
+
+pub use agent::{AgentConfig, AppTarget, ConnectorAgent};
+pub use hub::{ConnectorHub, ConnectorStatus, HubConfig, LiveConnector};
+pub use mux::{relay, Mux, MuxConfig, MuxStream};
 
 use crate::{Session, Resource, Identity};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 /// Application connector manager
 pub struct ConnectorManager {
@@ -14,6 +31,8 @@ pub struct ConnectorManager {
     tunnels: dashmap::DashMap<String, MicroTunnel>,
     /// Session bindings
     session_bindings: dashmap::DashMap<String, Vec<String>>,
+    /// Live reverse-tunnel connectors
+    hub: Option<Arc<ConnectorHub>>,
 }
 
 #[derive(Debug, Clone)]
@@ -127,9 +146,20 @@ impl ConnectorManager {
             connectors: dashmap::DashMap::new(),
             tunnels: dashmap::DashMap::new(),
             session_bindings: dashmap::DashMap::new(),
+            hub: None,
         }
     }
     
+    /// Route tunnels through live connectors registered with `hub`
+    pub fn with_hub(mut self, hub: Arc<ConnectorHub>) -> Self {
+        self.hub = Some(hub);
+        self
+    }
+    
+    pub fn hub(&self) -> Option<&Arc<ConnectorHub>> {
+        self.hub.as_ref()
+    }
+    
     /// Register application connector
     pub fn register_connector(&self, connector: ApplicationConnector) {
         tracing::info!(
@@ -155,20 +185,31 @@ impl ConnectorManager {
         resource: &Resource,
         protocol: TunnelProtocol,
     ) -> Result<MicroTunnel, ConnectorError> {
-        // Find suitable connector
-        let connectors = self.get_connectors(&resource.id);
-        let connector = connectors.first()
-            .ok_or(ConnectorError::NoConnectorAvailable)?;
+        // Prefer a live connector; fall back to statically registered ones
+        let live = self.hub.as_ref()
+            .and_then(|hub| hub.candidates(&resource.id).into_iter().next());
+        let (connector_id, destination) = match live {
+            Some(connector) => (
+                connector.connector_id.clone(),
+                connector.peer.unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0))),
+            ),
+            None => {
+                let connectors = self.get_connectors(&resource.id);
+                let connector = connectors.first()
+                    .ok_or(ConnectorError::NoConnectorAvailable)?;
+                (connector.id.clone(), connector.endpoint)
+            }
+        };
         
         // Create tunnel
         let tunnel = MicroTunnel {
             id: uuid::Uuid::new_v4().to_string(),
             session_id: session.id.clone(),
-            connector_id: connector.id.clone(),
+            connector_id,
             application_id: resource.id.clone(),
             user_id: session.identity.user_id.clone(),
             source: "0.0.0.0:0".parse().unwrap(),
-            destination: connector.endpoint,
+            destination,
             protocol,
             state: TunnelState::Establishing,
             created_at: chrono::Utc::now(),
@@ -209,6 +250,29 @@ impl ConnectorManager {
         }
     }
     
+    /// Open a stream for a tunnel through the live connectors and activate
+    /// the tunnel. Fails over to another connector if the chosen one cannot
+    /// reach the application.
+    pub async fn open_tunnel_stream(&self, tunnel_id: &str) -> Result<MuxStream, ConnectorError> {
+        let hub = self.hub.as_ref().ok_or(ConnectorError::NoConnectorAvailable)?;
+        let (application_id, user_id) = self.tunnels.get(tunnel_id)
+            .map(|t| (t.application_id.clone(), t.user_id.clone()))
+            .ok_or(ConnectorError::TunnelNotFound)?;
+        
+        let (connector, stream) = hub.open(&application_id, tunnel_id, &user_id).await?;
+        
+        if let Some(mut tunnel) = self.tunnels.get_mut(tunnel_id) {
+            tunnel.connector_id = connector.connector_id.clone();
+            if let Some(peer) = connector.peer {
+                tunnel.destination = peer;
+            }
+            tunnel.state = TunnelState::Active;
+            tunnel.last_activity = chrono::Utc::now();
+        }
+        
+        Ok(stream)
+    }
+    
     /// Close tunnel
     pub async fn close_tunnel(&self, tunnel_id: &str) {
         if let Some(mut tunnel) = self.tunnels.get_mut(tunnel_id) {
@@ -255,6 +319,11 @@ impl ConnectorManager {
             .filter(|c| c.health == ConnectorHealth::Healthy)
             .count();
         
+        if let Some(hub) = &self.hub {
+            stats.live_connectors = hub.len();
+            stats.live_streams = hub.active_streams();
+        }
+        
         for tunnel in self.tunnels.iter() {
             stats.total_tunnels += 1;
             match tunnel.state {
@@ -280,6 +349,8 @@ impl Default for ConnectorManager {
 pub struct ConnectorStats {
     pub total_connectors: usize,
     pub healthy_connectors: usize,
+    pub live_connectors: usize,
+    pub live_streams: usize,
     pub total_tunnels: usize,
     pub active_tunnels: usize,
     pub establishing_tunnels: usize,
@@ -293,6 +364,12 @@ pub enum ConnectorError {
     TunnelNotFound,
     ConnectionFailed,
     ProtocolNotSupported,
+    Io(String),
+    Protocol(String),
+    Registration(String),
+    StreamRejected(String),
+    Timeout,
+    ConnectionClosed,
 }
 
 impl std::fmt::Display for ConnectorError {
@@ -302,8 +379,20 @@ impl std::fmt::Display for ConnectorError {
             Self::TunnelNotFound => write!(f, "Tunnel not found"),
             Self::ConnectionFailed => write!(f, "Connection failed"),
             Self::ProtocolNotSupported => write!(f, "Protocol not supported"),
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Protocol(e) => write!(f, "Protocol error: {}", e),
+            Self::Registration(e) => write!(f, "Registration failed: {}", e),
+            Self::StreamRejected(e) => write!(f, "Stream rejected: {}", e),
+            Self::Timeout => write!(f, "Timed out"),
+            Self::ConnectionClosed => write!(f, "Connection closed"),
         }
     }
 }
 
 impl std::error::Error for ConnectorError {}
+
+impl From<std::io::Error> for ConnectorError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
@@ -0,0 +1,678 @@
+//! Stream Multiplexer
+//!
+//! Runs many user flows over one connector link. A reader task dispatches
+//! inbound frames to per-stream queues and a writer task serialises
+//! outbound frames, batching flushes. Senders spend per-stream credit and
+//! block when the peer has not yet consumed what it was sent.
+
+use super::protocol::{
+    read_frame, write_frame, Frame, FrameType, Heartbeat, HeartbeatAck, OpenStream, StreamError,
+    CONTROL_STREAM, INITIAL_WINDOW, MAX_DATA_CHUNK,
+};
+use super::ConnectorError;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot, Notify, Semaphore};
+
+#[derive(Debug, Clone)]
+pub struct MuxConfig {
+    /// Streams the peer may open towards us
+    pub max_streams: usize,
+    /// Outbound frames buffered before senders wait
+    pub outbound_queue: usize,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self {
+            max_streams: 1024,
+            outbound_queue: 1024,
+        }
+    }
+}
+
+/// Link-level events for the owner of a multiplexer
+#[derive(Debug)]
+pub enum MuxEvent {
+    /// Peer asked to open a stream
+    Incoming(IncomingStream),
+    Heartbeat(Heartbeat),
+    HeartbeatAck(HeartbeatAck),
+    /// Peer is draining
+    GoAway(String),
+    /// Link is gone; all streams have been torn down
+    Closed,
+}
+
+struct StreamSlot {
+    /// Dropped on remote close or reset so the reader sees end of stream
+    inbound: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    credit: Arc<Semaphore>,
+    local_closed: bool,
+}
+
+struct Shared {
+    out: mpsc::Sender<Frame>,
+    streams: dashmap::DashMap<u32, StreamSlot>,
+    pending_opens: dashmap::DashMap<u32, oneshot::Sender<Result<(), String>>>,
+    events: mpsc::UnboundedSender<MuxEvent>,
+    next_stream_id: AtomicU32,
+    closed: AtomicBool,
+    draining: AtomicBool,
+    shutdown: Notify,
+    /// Stops the writer so the transport closes even while streams are held
+    stop_writer: Arc<Notify>,
+    config: MuxConfig,
+}
+
+impl Shared {
+    fn new_stream(self: &Arc<Self>, id: u32) -> MuxStream {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let credit = Arc::new(Semaphore::new(INITIAL_WINDOW as usize));
+        self.streams.insert(id, StreamSlot {
+            inbound: Some(tx),
+            credit: credit.clone(),
+            local_closed: false,
+        });
+
+        MuxStream {
+            id,
+            reader: StreamReader { id, shared: self.clone(), inbound: rx },
+            writer: StreamWriter { id, shared: self.clone(), credit, closed: false },
+        }
+    }
+
+    fn remote_close(&self, id: u32) {
+        let finished = match self.streams.get_mut(&id) {
+            Some(mut slot) => {
+                slot.inbound = None;
+                slot.local_closed
+            }
+            None => false,
+        };
+        if finished {
+            self.streams.remove(&id);
+        }
+    }
+
+    fn local_close(&self, id: u32) {
+        let finished = match self.streams.get_mut(&id) {
+            Some(mut slot) => {
+                slot.local_closed = true;
+                slot.inbound.is_none()
+            }
+            None => false,
+        };
+        if finished {
+            self.streams.remove(&id);
+        }
+    }
+
+    fn reset(&self, id: u32) {
+        if let Some((_, slot)) = self.streams.remove(&id) {
+            slot.credit.close();
+        }
+    }
+
+    fn dispatch(self: &Arc<Self>, frame: Frame) {
+        let id = frame.stream_id;
+        match frame.frame_type {
+            FrameType::Data => {
+                if let Some(slot) = self.streams.get(&id) {
+                    if let Some(inbound) = &slot.inbound {
+                        let _ = inbound.send(frame.payload);
+                    }
+                }
+            }
+            FrameType::WindowUpdate => match frame.credit() {
+                Ok(credit) => {
+                    if let Some(slot) = self.streams.get(&id) {
+                        // Never hold more credit than the window the peer advertised
+                        let room = (INITIAL_WINDOW as usize).saturating_sub(slot.credit.available_permits());
+                        slot.credit.add_permits((credit as usize).min(room));
+                    }
+                }
+                Err(e) => tracing::warn!("Connector link: {}", e),
+            },
+            FrameType::Close => self.remote_close(id),
+            FrameType::Reset => {
+                if let Some((_, pending)) = self.pending_opens.remove(&id) {
+                    let _ = pending.send(Err(frame.reason()));
+                }
+                self.reset(id);
+            }
+            FrameType::OpenAck => {
+                if let Some((_, pending)) = self.pending_opens.remove(&id) {
+                    let _ = pending.send(Ok(()));
+                }
+            }
+            FrameType::OpenFail => {
+                if let Some((_, pending)) = self.pending_opens.remove(&id) {
+                    let _ = pending.send(Err(frame.reason()));
+                }
+                self.reset(id);
+            }
+            FrameType::Open => self.accept_open(frame),
+            FrameType::Heartbeat => match frame.decode() {
+                Ok(heartbeat) => {
+                    let _ = self.events.send(MuxEvent::Heartbeat(heartbeat));
+                }
+                Err(e) => tracing::warn!("Connector link: {}", e),
+            },
+            FrameType::HeartbeatAck => match frame.decode() {
+                Ok(ack) => {
+                    let _ = self.events.send(MuxEvent::HeartbeatAck(ack));
+                }
+                Err(e) => tracing::warn!("Connector link: {}", e),
+            },
+            FrameType::GoAway => {
+                self.draining.store(true, Ordering::SeqCst);
+                let _ = self.events.send(MuxEvent::GoAway(frame.reason()));
+            }
+            FrameType::Register | FrameType::RegisterAck => {
+                tracing::warn!("Connector link: unexpected {:?} after handshake", frame.frame_type);
+            }
+        }
+    }
+
+    fn accept_open(self: &Arc<Self>, frame: Frame) {
+        let id = frame.stream_id;
+        let refuse = |reason: &str| {
+            let _ = self.out.try_send(Frame::control(
+                FrameType::OpenFail,
+                id,
+                &StreamError { reason: reason.to_string() },
+            ));
+        };
+
+        let request: OpenStream = match frame.decode() {
+            Ok(request) => request,
+            Err(e) => return refuse(&e.to_string()),
+        };
+        if self.draining.load(Ordering::SeqCst) {
+            return refuse("connector draining");
+        }
+        if self.streams.contains_key(&id) {
+            return refuse("stream id in use");
+        }
+        if self.streams.len() >= self.config.max_streams {
+            return refuse("too many streams");
+        }
+
+        let stream = self.new_stream(id);
+        let _ = self.events.send(MuxEvent::Incoming(IncomingStream { stream, request }));
+    }
+
+    /// Tear down every stream and wake anyone waiting on the link
+    fn close_link(&self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        for slot in self.streams.iter() {
+            slot.credit.close();
+        }
+        self.streams.clear();
+        self.pending_opens.clear();
+        self.shutdown.notify_one();
+        self.stop_writer.notify_one();
+        let _ = self.events.send(MuxEvent::Closed);
+    }
+}
+
+/// Multiplexed connector link
+#[derive(Clone)]
+pub struct Mux {
+    shared: Arc<Shared>,
+}
+
+impl Mux {
+    /// Take over both halves of an established, registered transport
+    pub fn start<R, W>(reader: R, writer: W, config: MuxConfig) -> (Self, mpsc::UnboundedReceiver<MuxEvent>)
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (out_tx, out_rx) = mpsc::channel(config.outbound_queue.max(1));
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+
+        let shared = Arc::new(Shared {
+            out: out_tx,
+            streams: dashmap::DashMap::new(),
+            pending_opens: dashmap::DashMap::new(),
+            events: events_tx,
+            next_stream_id: AtomicU32::new(1),
+            closed: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
+            shutdown: Notify::new(),
+            stop_writer: Arc::new(Notify::new()),
+            config,
+        });
+
+        tokio::spawn(write_loop(writer, out_rx, shared.stop_writer.clone(), Arc::downgrade(&shared)));
+        tokio::spawn(read_loop(reader, shared.clone()));
+
+        (Self { shared }, events_rx)
+    }
+
+    /// Open a stream and wait for the peer to connect it
+    pub async fn open(&self, request: &OpenStream, timeout: Duration) -> Result<MuxStream, ConnectorError> {
+        if self.is_closed() {
+            return Err(ConnectorError::ConnectionClosed);
+        }
+        if self.shared.draining.load(Ordering::SeqCst) {
+            return Err(ConnectorError::StreamRejected("connector draining".to_string()));
+        }
+
+        let id = self.shared.next_stream_id.fetch_add(2, Ordering::SeqCst);
+        let mut stream = self.shared.new_stream(id);
+        let (tx, rx) = oneshot::channel();
+        self.shared.pending_opens.insert(id, tx);
+
+        self.send(Frame::control(FrameType::Open, id, request)).await?;
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(Ok(()))) => Ok(stream),
+            Ok(Ok(Err(reason))) => {
+                // Peer already forgot the stream
+                stream.writer.closed = true;
+                Err(ConnectorError::StreamRejected(reason))
+            }
+            Ok(Err(_)) => Err(ConnectorError::ConnectionClosed),
+            Err(_) => {
+                self.shared.pending_opens.remove(&id);
+                Err(ConnectorError::Timeout)
+            }
+        }
+    }
+
+    /// Queue a control frame
+    pub async fn send(&self, frame: Frame) -> Result<(), ConnectorError> {
+        self.shared.out.send(frame).await.map_err(|_| ConnectorError::ConnectionClosed)
+    }
+
+    /// Tell the peer no new streams will be accepted
+    pub async fn go_away(&self, reason: &str) {
+        self.shared.draining.store(true, Ordering::SeqCst);
+        let _ = self.send(Frame::control(
+            FrameType::GoAway,
+            CONTROL_STREAM,
+            &StreamError { reason: reason.to_string() },
+        )).await;
+    }
+
+    /// Drop the link and every stream on it
+    pub fn shutdown(&self) {
+        self.shared.close_link();
+    }
+
+    pub fn active_streams(&self) -> usize {
+        self.shared.streams.len()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::SeqCst)
+    }
+
+    /// Either side has sent or received `GoAway`
+    pub fn is_draining(&self) -> bool {
+        self.shared.draining.load(Ordering::SeqCst)
+    }
+}
+
+async fn read_loop<R: AsyncRead + Unpin>(mut reader: R, shared: Arc<Shared>) {
+    loop {
+        tokio::select! {
+            frame = read_frame(&mut reader) => match frame {
+                Ok(Some(frame)) => shared.dispatch(frame),
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("Connector link read failed: {}", e);
+                    break;
+                }
+            },
+            _ = shared.shutdown.notified() => break,
+        }
+    }
+    shared.close_link();
+}
+
+async fn write_loop<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut frames: mpsc::Receiver<Frame>,
+    stop: Arc<Notify>,
+    shared: Weak<Shared>,
+) {
+    'outer: loop {
+        let frame = tokio::select! {
+            frame = frames.recv() => match frame {
+                Some(frame) => frame,
+                None => break,
+            },
+            _ = stop.notified() => break,
+        };
+        // Write whatever is queued, then flush once
+        let mut next = Some(frame);
+        while let Some(frame) = next.take() {
+            if let Err(e) = write_frame(&mut writer, &frame).await {
+                tracing::warn!("Connector link write failed: {}", e);
+                break 'outer;
+            }
+            next = frames.try_recv().ok();
+        }
+        if writer.flush().await.is_err() {
+            break;
+        }
+    }
+
+    let _ = writer.shutdown().await;
+    if let Some(shared) = shared.upgrade() {
+        shared.close_link();
+    }
+}
+
+// =============================================================================
+// Streams
+// =============================================================================
+
+/// Stream offered by the peer, not yet accepted
+#[derive(Debug)]
+pub struct IncomingStream {
+    stream: MuxStream,
+    pub request: OpenStream,
+}
+
+impl IncomingStream {
+    /// Confirm the stream once the application is connected
+    pub async fn accept(self) -> Result<MuxStream, ConnectorError> {
+        let id = self.stream.id;
+        self.stream.writer.shared.out
+            .send(Frame::control(FrameType::OpenAck, id, &()))
+            .await
+            .map_err(|_| ConnectorError::ConnectionClosed)?;
+        Ok(self.stream)
+    }
+
+    /// Refuse the stream
+    pub async fn reject(self, reason: &str) {
+        let mut stream = self.stream;
+        stream.writer.closed = true;
+        let shared = stream.writer.shared.clone();
+        shared.reset(stream.id);
+        let _ = shared.out.send(Frame::control(
+            FrameType::OpenFail,
+            stream.id,
+            &StreamError { reason: reason.to_string() },
+        )).await;
+    }
+}
+
+/// One multiplexed flow
+#[derive(Debug)]
+pub struct MuxStream {
+    id: u32,
+    reader: StreamReader,
+    writer: StreamWriter,
+}
+
+impl MuxStream {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn split(self) -> (StreamReader, StreamWriter) {
+        (self.reader, self.writer)
+    }
+
+    pub async fn send(&mut self, data: &[u8]) -> Result<(), ConnectorError> {
+        self.writer.send(data).await
+    }
+
+    pub async fn recv(&mut self) -> Option<Vec<u8>> {
+        self.reader.recv().await
+    }
+
+    pub async fn close(&mut self) {
+        self.writer.close().await
+    }
+}
+
+pub struct StreamReader {
+    id: u32,
+    shared: Arc<Shared>,
+    inbound: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+impl StreamReader {
+    /// Next chunk from the peer; `None` once the peer closed or reset
+    pub async fn recv(&mut self) -> Option<Vec<u8>> {
+        let chunk = self.inbound.recv().await?;
+        // Hand the credit back now the bytes have left the queue
+        let _ = self.shared.out.send(Frame::window_update(self.id, chunk.len() as u32)).await;
+        Some(chunk)
+    }
+}
+
+impl std::fmt::Debug for StreamReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamReader").field("id", &self.id).finish()
+    }
+}
+
+pub struct StreamWriter {
+    id: u32,
+    shared: Arc<Shared>,
+    credit: Arc<Semaphore>,
+    closed: bool,
+}
+
+impl StreamWriter {
+    pub async fn send(&mut self, data: &[u8]) -> Result<(), ConnectorError> {
+        if self.closed {
+            return Err(ConnectorError::ConnectionClosed);
+        }
+        for chunk in data.chunks(MAX_DATA_CHUNK) {
+            self.credit.acquire_many(chunk.len() as u32).await
+                .map_err(|_| ConnectorError::ConnectionClosed)?
+                .forget();
+            self.shared.out.send(Frame::data(self.id, chunk.to_vec())).await
+                .map_err(|_| ConnectorError::ConnectionClosed)?;
+        }
+        Ok(())
+    }
+
+    /// Half-close: no more data from this side
+    pub async fn close(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.closed = true;
+        let _ = self.shared.out.send(Frame::close(self.id)).await;
+        self.shared.local_close(self.id);
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        // Dropped without a clean close: abort the stream on both sides
+        if !self.closed {
+            let _ = self.shared.out.try_send(Frame::reset(self.id, "stream aborted"));
+            self.shared.reset(self.id);
+        }
+    }
+}
+
+impl std::fmt::Debug for StreamWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamWriter")
+            .field("id", &self.id)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+/// Pump bytes between a stream and a socket until both directions finish.
+/// Returns (bytes socket → stream, bytes stream → socket).
+pub async fn relay<S>(stream: MuxStream, socket: S) -> Result<(u64, u64), ConnectorError>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (mut reader, mut writer) = stream.split();
+    let (mut socket_reader, mut socket_writer) = tokio::io::split(socket);
+
+    let upstream = async {
+        let mut buf = vec![0u8; MAX_DATA_CHUNK];
+        let mut total = 0u64;
+        loop {
+            let n = socket_reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            writer.send(&buf[..n]).await?;
+            total += n as u64;
+        }
+        writer.close().await;
+        Ok::<u64, ConnectorError>(total)
+    };
+
+    let downstream = async {
+        let mut total = 0u64;
+        while let Some(chunk) = reader.recv().await {
+            socket_writer.write_all(&chunk).await?;
+            total += chunk.len() as u64;
+        }
+        let _ = socket_writer.shutdown().await;
+        Ok::<u64, ConnectorError>(total)
+    };
+
+    tokio::try_join!(upstream, downstream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PoP and connector ends of one link
+    fn link(config: MuxConfig) -> ((Mux, mpsc::UnboundedReceiver<MuxEvent>), (Mux, mpsc::UnboundedReceiver<MuxEvent>)) {
+        let (pop, connector) = tokio::io::duplex(64 * 1024);
+        let (pop_r, pop_w) = tokio::io::split(pop);
+        let (conn_r, conn_w) = tokio::io::split(connector);
+        (
+            Mux::start(pop_r, pop_w, MuxConfig::default()),
+            Mux::start(conn_r, conn_w, config),
+        )
+    }
+
+    fn request(app: &str) -> OpenStream {
+        OpenStream {
+            application_id: app.to_string(),
+            tunnel_id: "t-1".to_string(),
+            user_id: "alice".to_string(),
+        }
+    }
+
+    async fn incoming(events: &mut mpsc::UnboundedReceiver<MuxEvent>) -> IncomingStream {
+        match events.recv().await {
+            Some(MuxEvent::Incoming(incoming)) => incoming,
+            other => panic!("expected incoming stream, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_exchange_and_half_close() {
+        let ((pop, _), (connector, mut events)) = link(MuxConfig::default());
+
+        let accept = tokio::spawn(async move {
+            let offered = incoming(&mut events).await;
+            assert_eq!(offered.request.application_id, "wiki");
+            let mut stream = offered.accept().await.unwrap();
+            let request = stream.recv().await.unwrap();
+            stream.send(&[request.as_slice(), b" ok"].concat()).await.unwrap();
+            stream.close().await;
+            // PoP half-closed too
+            assert!(stream.recv().await.is_none());
+            (connector, events)
+        });
+
+        let mut stream = pop.open(&request("wiki"), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(stream.id() % 2, 1, "PoP opens odd stream ids");
+        stream.send(b"GET /").await.unwrap();
+        assert_eq!(stream.recv().await.unwrap(), b"GET / ok");
+        assert!(stream.recv().await.is_none(), "connector closed its side");
+        stream.close().await;
+
+        let (connector, _events) = accept.await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pop.active_streams(), 0);
+        assert_eq!(connector.active_streams(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_and_refused_opens() {
+        let ((pop, _), (connector, mut events)) = link(MuxConfig { max_streams: 1, ..Default::default() });
+
+        let reject = tokio::spawn(async move {
+            incoming(&mut events).await.reject("app unreachable").await;
+            let held = incoming(&mut events).await.accept().await.unwrap();
+            (events, held)
+        });
+        let err = pop.open(&request("db"), Duration::from_secs(5)).await.unwrap_err();
+        assert!(matches!(err, ConnectorError::StreamRejected(ref r) if r == "app unreachable"), "{:?}", err);
+
+        let _first = pop.open(&request("db"), Duration::from_secs(5)).await.unwrap();
+        let (_events, _held) = reject.await.unwrap();
+        // Connector is at its stream limit
+        let err = pop.open(&request("db"), Duration::from_secs(5)).await.unwrap_err();
+        assert!(matches!(err, ConnectorError::StreamRejected(ref r) if r == "too many streams"), "{:?}", err);
+
+        connector.go_away("upgrading").await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(pop.is_draining());
+        let err = pop.open(&request("db"), Duration::from_secs(5)).await.unwrap_err();
+        assert!(matches!(err, ConnectorError::StreamRejected(_)));
+    }
+
+    #[tokio::test]
+    async fn test_send_blocks_without_credit() {
+        let ((pop, _), (_connector, mut events)) = link(MuxConfig::default());
+
+        let accept = tokio::spawn(async move {
+            let stream = incoming(&mut events).await.accept().await.unwrap();
+            (stream, events)
+        });
+        let mut stream = pop.open(&request("files"), Duration::from_secs(5)).await.unwrap();
+        let (mut remote, _events) = accept.await.unwrap();
+
+        // The whole window goes through; one byte more waits for the reader
+        stream.send(&vec![0u8; INITIAL_WINDOW as usize]).await.unwrap();
+        let blocked = tokio::time::timeout(Duration::from_millis(100), stream.send(b"x")).await;
+        assert!(blocked.is_err(), "send must wait for a window update");
+
+        let chunk = remote.recv().await.unwrap();
+        assert_eq!(chunk.len(), MAX_DATA_CHUNK);
+        tokio::time::timeout(Duration::from_secs(5), stream.send(b"x")).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_link_loss_closes_streams() {
+        let ((pop, mut pop_events), (connector, mut events)) = link(MuxConfig::default());
+
+        let accept = tokio::spawn(async move {
+            let stream = incoming(&mut events).await.accept().await.unwrap();
+            (stream, events)
+        });
+        let mut stream = pop.open(&request("ssh"), Duration::from_secs(5)).await.unwrap();
+        let _remote = accept.await.unwrap();
+
+        connector.shutdown();
+        assert!(matches!(pop_events.recv().await, Some(MuxEvent::Closed)));
+        assert!(pop.is_closed());
+        assert!(stream.recv().await.is_none());
+        assert!(matches!(stream.send(b"late").await, Err(ConnectorError::ConnectionClosed)));
+        assert!(matches!(
+            pop.open(&request("ssh"), Duration::from_secs(1)).await,
+            Err(ConnectorError::ConnectionClosed)
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! Connector Wire Protocol
+//!
+//! Length-prefixed frames exchanged between an app connector and the PoP
+//! over a single outbound connection (mTLS today, any reliable ordered
+//! byte stream such as a QUIC stream works the same way).
+//!
+//! ```text
+//! +---------+------+-----------+-------------+-----------------+
+//! | version | type | stream id | payload len | payload         |
+//! |   u8    |  u8  |  u32 BE   |   u32 BE    | len bytes       |
+//! +---------+------+-----------+-------------+-----------------+
+//! ```
+//!
+//! Control payloads are JSON; `Data` carries raw bytes. Stream 0 is the
+//! control channel. Only the PoP opens streams (odd ids), one per
+//! authorised user flow, and each direction of a stream is flow-controlled
+//! by `WindowUpdate` credits starting at [`INITIAL_WINDOW`].
+
+use super::ConnectorError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Largest payload accepted in one frame
+pub const MAX_FRAME_PAYLOAD: usize = 256 * 1024;
+
+/// Largest `Data` payload a sender produces
+pub const MAX_DATA_CHUNK: usize = 16 * 1024;
+
+/// Per-stream, per-direction credit before any `WindowUpdate`
+pub const INITIAL_WINDOW: u32 = 256 * 1024;
+
+/// Control channel stream id
+pub const CONTROL_STREAM: u32 = 0;
+
+const HEADER_LEN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameType {
+    /// Connector → PoP: identity and published applications
+    Register = 0x01,
+    /// PoP → connector: registration outcome
+    RegisterAck = 0x02,
+    /// Connector → PoP: liveness and load report
+    Heartbeat = 0x03,
+    /// PoP → connector: heartbeat echo
+    HeartbeatAck = 0x04,
+    /// PoP → connector: open a stream to an application
+    Open = 0x10,
+    /// Connector → PoP: stream connected to the application
+    OpenAck = 0x11,
+    /// Connector → PoP: application unreachable
+    OpenFail = 0x12,
+    Data = 0x13,
+    /// Grants the peer more send credit on a stream
+    WindowUpdate = 0x14,
+    /// Sender is done writing (half-close)
+    Close = 0x15,
+    /// Abort a stream in both directions
+    Reset = 0x16,
+    /// Sender is draining; no new streams
+    GoAway = 0x20,
+}
+
+impl FrameType {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0x01 => Self::Register,
+            0x02 => Self::RegisterAck,
+            0x03 => Self::Heartbeat,
+            0x04 => Self::HeartbeatAck,
+            0x10 => Self::Open,
+            0x11 => Self::OpenAck,
+            0x12 => Self::OpenFail,
+            0x13 => Self::Data,
+            0x14 => Self::WindowUpdate,
+            0x15 => Self::Close,
+            0x16 => Self::Reset,
+            0x20 => Self::GoAway,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub frame_type: FrameType,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Frame with a JSON payload
+    pub fn control<T: Serialize>(frame_type: FrameType, stream_id: u32, message: &T) -> Self {
+        Self {
+            frame_type,
+            stream_id,
+            payload: serde_json::to_vec(message).unwrap_or_default(),
+        }
+    }
+
+    pub fn data(stream_id: u32, payload: Vec<u8>) -> Self {
+        Self { frame_type: FrameType::Data, stream_id, payload }
+    }
+
+    pub fn window_update(stream_id: u32, credit: u32) -> Self {
+        Self {
+            frame_type: FrameType::WindowUpdate,
+            stream_id,
+            payload: credit.to_be_bytes().to_vec(),
+        }
+    }
+
+    pub fn close(stream_id: u32) -> Self {
+        Self { frame_type: FrameType::Close, stream_id, payload: Vec::new() }
+    }
+
+    pub fn reset(stream_id: u32, reason: &str) -> Self {
+        Self::control(FrameType::Reset, stream_id, &StreamError { reason: reason.to_string() })
+    }
+
+    /// Decode a JSON payload
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, ConnectorError> {
+        serde_json::from_slice(&self.payload).map_err(|e| {
+            ConnectorError::Protocol(format!("invalid {:?} payload: {}", self.frame_type, e))
+        })
+    }
+
+    /// Credit carried by a `WindowUpdate`
+    pub fn credit(&self) -> Result<u32, ConnectorError> {
+        let bytes: [u8; 4] = self.payload.as_slice()
+            .try_into()
+            .map_err(|_| ConnectorError::Protocol("invalid window update".to_string()))?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    /// Reason carried by `Reset`, `OpenFail` or `GoAway`
+    pub fn reason(&self) -> String {
+        self.decode::<StreamError>()
+            .map(|e| e.reason)
+            .unwrap_or_else(|_| String::from_utf8_lossy(&self.payload).into_owned())
+    }
+}
+
+/// Read one frame; `None` on a clean end of stream between frames
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Frame>, ConnectorError> {
+    let mut header = [0u8; HEADER_LEN];
+    match reader.read_exact(&mut header[..1]).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    reader.read_exact(&mut header[1..]).await?;
+
+    if header[0] != PROTOCOL_VERSION {
+        return Err(ConnectorError::Protocol(format!("unsupported protocol version {}", header[0])));
+    }
+    let frame_type = FrameType::from_u8(header[1])
+        .ok_or_else(|| ConnectorError::Protocol(format!("unknown frame type {:#04x}", header[1])))?;
+    let stream_id = u32::from_be_bytes([header[2], header[3], header[4], header[5]]);
+    let len = u32::from_be_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    if len > MAX_FRAME_PAYLOAD {
+        return Err(ConnectorError::Protocol(format!("frame of {} bytes exceeds limit", len)));
+    }
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+
+    Ok(Some(Frame { frame_type, stream_id, payload }))
+}
+
+/// Write one frame without flushing
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> Result<(), ConnectorError> {
+    if frame.payload.len() > MAX_FRAME_PAYLOAD {
+        return Err(ConnectorError::Protocol("frame payload too large".to_string()));
+    }
+
+    let mut buf = Vec::with_capacity(HEADER_LEN + frame.payload.len());
+    buf.push(PROTOCOL_VERSION);
+    buf.push(frame.frame_type as u8);
+    buf.extend_from_slice(&frame.stream_id.to_be_bytes());
+    buf.extend_from_slice(&(frame.payload.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&frame.payload);
+
+    writer.write_all(&buf).await?;
+    Ok(())
+}
+
+/// Write and flush one frame (handshake)
+pub async fn send_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> Result<(), ConnectorError> {
+    write_frame(writer, frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+// =============================================================================
+// Control Messages
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Register {
+    pub connector_id: String,
+    pub agent_version: String,
+    /// Application ids this connector can reach
+    pub applications: Vec<String>,
+    /// Concurrent streams the connector will accept
+    pub max_streams: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterAck {
+    pub accepted: bool,
+    pub session_id: String,
+    pub heartbeat_interval_secs: u64,
+    /// Applications the connector may not publish
+    #[serde(default)]
+    pub rejected_applications: Vec<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub sequence: u64,
+    pub active_streams: u32,
+    /// Published applications the connector currently cannot reach
+    #[serde(default)]
+    pub unhealthy_applications: Vec<String>,
+    /// Round trip of the previous heartbeat
+    #[serde(default)]
+    pub rtt_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatAck {
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenStream {
+    pub application_id: String,
+    pub tunnel_id: String,
+    pub user_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamError {
+    pub reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frame_round_trip() {
+        let (mut a, mut b) = tokio::io::duplex(4096);
+        let open = OpenStream {
+            application_id: "app-1".to_string(),
+            tunnel_id: "t-1".to_string(),
+            user_id: "alice".to_string(),
+        };
+        send_frame(&mut a, &Frame::control(FrameType::Open, 3, &open)).await.unwrap();
+        send_frame(&mut a, &Frame::window_update(3, 4096)).await.unwrap();
+        send_frame(&mut a, &Frame::reset(3, "backend down")).await.unwrap();
+        drop(a);
+
+        let frame = read_frame(&mut b).await.unwrap().unwrap();
+        assert_eq!((frame.frame_type, frame.stream_id), (FrameType::Open, 3));
+        assert_eq!(frame.decode::<OpenStream>().unwrap().user_id, "alice");
+
+        let frame = read_frame(&mut b).await.unwrap().unwrap();
+        assert_eq!(frame.credit().unwrap(), 4096);
+
+        let frame = read_frame(&mut b).await.unwrap().unwrap();
+        assert_eq!(frame.reason(), "backend down");
+
+        // Clean end of stream between frames
+        assert!(read_frame(&mut b).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_frames_rejected() {
+        let header = |version: u8, frame_type: u8, len: u32| {
+            let mut buf = vec![version, frame_type, 0, 0, 0, 1];
+            buf.extend_from_slice(&len.to_be_bytes());
+            buf
+        };
+
+        let mut bad_version = &header(2, 0x13, 0)[..];
+        assert!(matches!(read_frame(&mut bad_version).await, Err(ConnectorError::Protocol(_))));
+
+        let mut bad_type = &header(PROTOCOL_VERSION, 0x7f, 0)[..];
+        assert!(matches!(read_frame(&mut bad_type).await, Err(ConnectorError::Protocol(_))));
+
+        let mut oversized = &header(PROTOCOL_VERSION, 0x13, MAX_FRAME_PAYLOAD as u32 + 1)[..];
+        assert!(matches!(read_frame(&mut oversized).await, Err(ConnectorError::Protocol(_))));
+
+        // Truncated payload is an error, not a clean close
+        let mut truncated = header(PROTOCOL_VERSION, 0x13, 8);
+        truncated.extend_from_slice(b"abc");
+        assert!(read_frame(&mut &truncated[..]).await.is_err());
+
+        let mut sink = Vec::new();
+        let too_big = Frame::data(1, vec![0; MAX_FRAME_PAYLOAD + 1]);
+        assert!(write_frame(&mut sink, &too_big).await.is_err());
+        assert!(Frame::data(1, vec![1, 2]).credit().is_err());
+    }
+}
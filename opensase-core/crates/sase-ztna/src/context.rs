@@ -175,7 +175,7 @@ impl ContextEvaluator {
         // Check if outside normal working hours and user typically works business hours
         if history.typical_access_hours.is_some() {
             let (start, end) = history.typical_access_hours.unwrap();
-            if hour < start || hour > end {
+            if hour < start as u32 || hour > end as u32 {
                 return Some(RiskSignal {
                     signal_type: RiskSignalType::UnusualTime,
                     severity: RiskSeverity::Low,
@@ -221,7 +221,7 @@ impl Default for UserHistory {
     }
 }
 
-fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+pub(crate) fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     const EARTH_RADIUS_KM: f64 = 6371.0;
     
     let lat1_rad = lat1.to_radians();
@@ -1,111 +1,302 @@
 //! Continuous Evaluation
 //!
-//! Real-time session monitoring and re-evaluation.
+//! Real-time session monitoring and re-evaluation. Every tick each
+//! monitored session is re-scored from the user's risk, its current
+//! signals and the device's latest posture; the score caps the session's
+//! trust level and, past the configured thresholds, forces step-up,
+//! suspends or revokes the session through the [`SessionManager`].
 
-use crate::{Session, SessionStatus, RiskSignal, RiskSignalType, RiskSeverity};
+use crate::{Session, SessionStatus, DevicePosture, RiskSignal, RiskSignalType, RiskSeverity, TrustLevel};
+use crate::audit::{AuditEventType, AuditLogger};
+use crate::risk::RiskEngine;
+use crate::session::SessionManager;
+use crate::stepup::{ChallengeType, StepUpManager, StepUpReason, StepUpState};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Continuous evaluation engine
 pub struct ContinuousEvaluator {
     /// Active sessions
     sessions: dashmap::DashMap<String, MonitoredSession>,
+    /// Session store acted on when risk crosses a threshold
+    session_manager: Arc<SessionManager>,
+    /// Audit trail for downgrades, step-ups, suspensions and revocations
+    audit: Arc<AuditLogger>,
     /// Evaluation interval
     interval_secs: u64,
-    /// Risk threshold for reevaluation
-    risk_threshold: f64,
+    /// Risk bands for step-up, suspension and revocation
+    thresholds: RiskThresholds,
+    /// Fresh posture and risk signals for monitored sessions
+    sources: Vec<Arc<dyn SessionSignalSource>>,
+    /// Inline step-up for high-risk sessions instead of suspension
+    step_up: Option<Arc<StepUpManager>>,
+}
+
+/// Risk scores (0-100) at which a monitored session is acted on. A
+/// critical signal revokes regardless of score.
+#[derive(Debug, Clone, Copy)]
+pub struct RiskThresholds {
+    /// Require step-up at the next request; sessions are suspended
+    /// instead when no step-up manager is configured
+    pub step_up: f64,
+    /// Suspend until the user reauthenticates
+    pub suspend: f64,
+    /// Revoke the session
+    pub revoke: f64,
+}
+
+impl Default for RiskThresholds {
+    fn default() -> Self {
+        Self {
+            step_up: 50.0,
+            suspend: 75.0,
+            revoke: 90.0,
+        }
+    }
+}
+
+/// Supplies fresh device posture and risk signals for monitored sessions,
+/// e.g. from an MDM/EDR feed or the SOC
+#[async_trait::async_trait]
+pub trait SessionSignalSource: Send + Sync {
+    /// Current posture of the session's device, if this source knows it
+    async fn posture(&self, _session: &Session) -> Option<DevicePosture> {
+        None
+    }
+
+    /// Risk signals currently raised against the session or its user
+    async fn signals(&self, session: &Session) -> Vec<RiskSignal>;
 }
 
 #[derive(Clone)]
 struct MonitoredSession {
-    session: Session,
     last_evaluation: chrono::DateTime<chrono::Utc>,
     evaluation_count: u32,
     current_risk: f64,
+    /// Signals raised at the last evaluation
     signals: Vec<RiskSignal>,
+    /// Risk taken off by a passed step-up, until a new signal appears
+    relief: f64,
+    /// Action taken at the last evaluation, to audit only transitions
+    last_action: EvaluationAction,
 }
 
 impl ContinuousEvaluator {
-    pub fn new(interval_secs: u64) -> Self {
+    pub fn new(interval_secs: u64, session_manager: Arc<SessionManager>, audit: Arc<AuditLogger>) -> Self {
         Self {
             sessions: dashmap::DashMap::new(),
+            session_manager,
+            audit,
             interval_secs,
-            risk_threshold: 50.0,
+            thresholds: RiskThresholds::default(),
+            sources: Vec::new(),
+            step_up: None,
         }
     }
-    
+
+    pub fn with_thresholds(mut self, thresholds: RiskThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Pull posture and signals from `source` at every evaluation
+    pub fn with_signal_source(mut self, source: Arc<dyn SessionSignalSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Challenge sessions in the step-up band at their next request rather
+    /// than suspending them; the suspend and revoke bands still apply
+    pub fn with_step_up(mut self, step_up: Arc<StepUpManager>) -> Self {
+        self.step_up = Some(step_up);
+        self
+    }
+
+    /// Time between evaluation passes
+    pub fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.interval_secs.max(1))
+    }
+
+    /// Lower a session's risk after a passed step-up. The relief lasts
+    /// until a signal the user has not already answered appears.
+    pub fn relieve(&self, session_id: &str, amount: f64) {
+        if let Some(mut monitored) = self.sessions.get_mut(session_id) {
+            monitored.relief += amount;
+            monitored.current_risk = (monitored.current_risk - amount).max(0.0);
+            monitored.last_action = EvaluationAction::Continue;
+        }
+    }
+
     /// Register session for monitoring
     pub async fn register_session(&self, session: &Session) {
-        self.sessions.insert(session.id.clone(), MonitoredSession {
-            session: session.clone(),
+        self.sessions.entry(session.id.clone()).or_insert_with(|| MonitoredSession {
             last_evaluation: chrono::Utc::now(),
             evaluation_count: 0,
             current_risk: session.risk_score,
             signals: vec![],
+            relief: 0.0,
+            last_action: EvaluationAction::Continue,
         });
     }
-    
+
     /// Unregister session
     pub async fn unregister_session(&self, session_id: &str) {
         self.sessions.remove(session_id);
     }
-    
-    /// Run continuous evaluation loop
-    pub async fn run(&self, risk_engine: Arc<crate::risk::RiskEngine>) {
-        let interval = std::time::Duration::from_secs(self.interval_secs);
-        
-        loop {
-            tokio::time::sleep(interval).await;
-            
-            for mut entry in self.sessions.iter_mut() {
-                let monitored = entry.value_mut();
-                
-                // Skip if session expired
-                if monitored.session.status != SessionStatus::Active {
-                    continue;
-                }
-                
-                // Check session expiration
-                if chrono::Utc::now() > monitored.session.expires_at {
-                    monitored.session.status = SessionStatus::Expired;
-                    continue;
+
+    /// Re-evaluate every monitored session once. Returns the sessions
+    /// acted on; revoked sessions are no longer monitored.
+    pub async fn evaluate_all(&self, risk_engine: &RiskEngine) -> Vec<EvaluationResult> {
+        let session_ids: Vec<String> = self.sessions.iter().map(|e| e.key().clone()).collect();
+
+        let mut results = Vec::new();
+        for session_id in session_ids {
+            if let Some(result) = self.evaluate(&session_id, risk_engine).await {
+                if result.action != EvaluationAction::Continue {
+                    results.push(result);
                 }
-                
-                // Check for new risk signals
-                let new_signals = self.check_for_signals(&monitored.session).await;
-                
-                if !new_signals.is_empty() {
-                    monitored.signals.extend(new_signals.clone());
-                    
-                    // Calculate updated risk
-                    let user_id = &monitored.session.identity.user_id;
-                    let user_risk = risk_engine.get_user_risk(user_id);
-                    
-                    let signal_risk: f64 = new_signals.iter()
-                        .map(|s| match s.severity {
-                            RiskSeverity::Low => 5.0,
-                            RiskSeverity::Medium => 15.0,
-                            RiskSeverity::High => 30.0,
-                            RiskSeverity::Critical => 50.0,
-                        })
-                        .sum();
-                    
-                    monitored.current_risk = (user_risk + signal_risk).min(100.0);
-                    
-                    // Take action if risk exceeds threshold
-                    if monitored.current_risk > self.risk_threshold {
-                        self.handle_high_risk(&mut monitored.session, &new_signals).await;
+            }
+        }
+        results
+    }
+
+    /// Force reevaluation of session
+    pub async fn reevaluate(&self, session_id: &str, risk_engine: &RiskEngine) -> Option<EvaluationResult> {
+        self.evaluate(session_id, risk_engine).await
+    }
+
+    async fn evaluate(&self, session_id: &str, risk_engine: &RiskEngine) -> Option<EvaluationResult> {
+        let previous = self.sessions.get(session_id).map(|m| m.clone())?;
+
+        // The session manager holds the authoritative session
+        let Some(mut session) = self.session_manager.get(session_id) else {
+            self.sessions.remove(session_id);
+            return None;
+        };
+        match session.status {
+            SessionStatus::Active => {}
+            // Waiting on reauthentication; nothing more to decide
+            SessionStatus::Suspended => return None,
+            SessionStatus::Revoked | SessionStatus::Expired => {
+                self.sessions.remove(session_id);
+                return None;
+            }
+        }
+        if chrono::Utc::now() > session.expires_at {
+            self.sessions.remove(session_id);
+            return None;
+        }
+
+        // Fresh posture first, so sources see the device as it is now
+        for source in &self.sources {
+            if let Some(posture) = source.posture(&session).await {
+                self.session_manager.update_posture(session_id, posture.clone()).await;
+                session.device.posture = posture;
+            }
+        }
+
+        let mut signals = self.check_for_signals(&session).await;
+        signals.extend(posture_signals(&session.device.posture));
+        for source in &self.sources {
+            signals.extend(source.signals(&session).await);
+        }
+
+        let new_signals: Vec<RiskSignal> = signals.iter()
+            .filter(|s| !previous.signals.iter().any(|p| same_signal(p, s)))
+            .cloned()
+            .collect();
+        let relief = if new_signals.is_empty() { previous.relief } else { 0.0 };
+
+        let signal_risk: f64 = signals.iter().map(|s| severity_weight(s.severity)).sum();
+        let user_risk = risk_engine.get_user_risk(&session.identity.user_id);
+        let risk = ((user_risk + signal_risk).min(100.0) - relief).max(0.0);
+
+        let action = self.action_for(risk, &signals);
+
+        for signal in &new_signals {
+            let mut details = HashMap::new();
+            details.insert("signal_type".to_string(), format!("{:?}", signal.signal_type));
+            details.insert("severity".to_string(), format!("{:?}", signal.severity));
+            details.insert("description".to_string(), signal.description.clone());
+            self.audit.log_session_risk(AuditEventType::RiskSignal, &session, risk, details).await;
+        }
+
+        // The score caps the session's trust; trust is only ever lowered here
+        let ceiling = trust_ceiling(risk);
+        if let Some(from) = self.session_manager.downgrade(session_id, ceiling, risk).await {
+            let mut details = HashMap::new();
+            details.insert("from".to_string(), format!("{:?}", from));
+            details.insert("to".to_string(), format!("{:?}", ceiling));
+            self.audit.log_session_risk(AuditEventType::TrustDowngraded, &session, risk, details).await;
+            tracing::info!("Session {} trust lowered from {:?} to {:?} (risk {:.1})", session_id, from, ceiling, risk);
+        }
+
+        let changed = action != previous.last_action;
+        match action {
+            EvaluationAction::Continue | EvaluationAction::RequireReauth => {}
+            EvaluationAction::StepUp => {
+                if let Some(step_up) = &self.step_up {
+                    let challenge_type = step_up_for(&signals);
+                    let state = step_up.require(session_id, StepUpReason::TrustDegradation, challenge_type, risk);
+                    if changed && matches!(state, StepUpState::Required { .. }) {
+                        let mut details = action_details(&signals);
+                        details.insert("challenge_type".to_string(), format!("{:?}", challenge_type));
+                        self.audit.log_session_risk(AuditEventType::StepUpRequired, &session, risk, details).await;
                     }
                 }
-                
+            }
+            EvaluationAction::Suspend => {
+                self.session_manager.suspend(session_id).await;
+                self.audit.log_session_risk(AuditEventType::SessionSuspended, &session, risk, action_details(&signals)).await;
+                tracing::warn!("Session {} suspended pending reauthentication (risk {:.1})", session_id, risk);
+            }
+            EvaluationAction::Revoke => {
+                self.session_manager.terminate(session_id).await;
+                self.sessions.remove(session_id);
+                self.audit.log_session_risk(AuditEventType::SessionTerminated, &session, risk, action_details(&signals)).await;
+                tracing::warn!("Session {} revoked (risk {:.1}): {:?}", session_id, risk, signals);
+            }
+        }
+
+        if action != EvaluationAction::Revoke {
+            if let Some(mut monitored) = self.sessions.get_mut(session_id) {
                 monitored.last_evaluation = chrono::Utc::now();
                 monitored.evaluation_count += 1;
+                monitored.current_risk = risk;
+                monitored.signals = signals.clone();
+                monitored.relief = relief;
+                monitored.last_action = action;
             }
         }
+
+        Some(EvaluationResult {
+            session_id: session_id.to_string(),
+            risk_score: risk,
+            signals,
+            action,
+        })
     }
-    
+
+    fn action_for(&self, risk: f64, signals: &[RiskSignal]) -> EvaluationAction {
+        let critical = signals.iter().any(|s| s.severity == RiskSeverity::Critical);
+        if critical || risk >= self.thresholds.revoke {
+            EvaluationAction::Revoke
+        } else if risk >= self.thresholds.suspend {
+            EvaluationAction::Suspend
+        } else if risk >= self.thresholds.step_up {
+            if self.step_up.is_some() {
+                EvaluationAction::StepUp
+            } else {
+                EvaluationAction::Suspend
+            }
+        } else {
+            EvaluationAction::Continue
+        }
+    }
+
     async fn check_for_signals(&self, session: &Session) -> Vec<RiskSignal> {
         let mut signals = Vec::new();
-        
+
         // Check session age
         let session_age = chrono::Utc::now() - session.created_at;
         if session_age.num_hours() > 8 {
@@ -116,7 +307,7 @@ impl ContinuousEvaluator {
                 detected_at: chrono::Utc::now(),
             });
         }
-        
+
         // Check inactivity
         let inactivity = chrono::Utc::now() - session.last_activity;
         if inactivity.num_minutes() > 30 {
@@ -127,50 +318,10 @@ impl ContinuousEvaluator {
                 detected_at: chrono::Utc::now(),
             });
         }
-        
+
         signals
     }
-    
-    async fn handle_high_risk(&self, session: &mut Session, signals: &[RiskSignal]) {
-        tracing::warn!(
-            "High risk detected for session {}: {:?}",
-            session.id, signals
-        );
-        
-        // Check for critical signals
-        let has_critical = signals.iter()
-            .any(|s| s.severity == RiskSeverity::Critical);
-        
-        if has_critical {
-            session.status = SessionStatus::Revoked;
-            tracing::warn!("Session {} revoked due to critical risk", session.id);
-        } else {
-            session.status = SessionStatus::Suspended;
-            tracing::warn!("Session {} suspended pending reauthentication", session.id);
-        }
-    }
-    
-    /// Force reevaluation of session
-    pub async fn reevaluate(&self, session_id: &str) -> Option<EvaluationResult> {
-        let mut entry = self.sessions.get_mut(session_id)?;
-        let monitored = entry.value_mut();
-        
-        let signals = self.check_for_signals(&monitored.session).await;
-        monitored.signals.extend(signals.clone());
-        monitored.last_evaluation = chrono::Utc::now();
-        
-        Some(EvaluationResult {
-            session_id: session_id.to_string(),
-            risk_score: monitored.current_risk,
-            signals,
-            action: if monitored.current_risk > self.risk_threshold {
-                EvaluationAction::RequireReauth
-            } else {
-                EvaluationAction::Continue
-            },
-        })
-    }
-    
+
     /// Get session status
     pub fn get_status(&self, session_id: &str) -> Option<SessionMonitorStatus> {
         self.sessions.get(session_id).map(|m| SessionMonitorStatus {
@@ -183,6 +334,77 @@ impl ContinuousEvaluator {
     }
 }
 
+/// Signals raised by a device's posture
+fn posture_signals(posture: &DevicePosture) -> Vec<RiskSignal> {
+    let checks = [
+        (posture.jailbroken, RiskSignalType::MalwareDetected, RiskSeverity::High, "Device is jailbroken or rooted"),
+        (!posture.antivirus_running, RiskSignalType::MalwareDetected, RiskSeverity::Medium, "Antivirus not running"),
+        (!posture.firewall_enabled, RiskSignalType::UnusualBehavior, RiskSeverity::Medium, "Firewall disabled"),
+        (!posture.disk_encrypted, RiskSignalType::UnusualBehavior, RiskSeverity::Low, "Disk not encrypted"),
+        (!posture.os_patched, RiskSignalType::UnusualBehavior, RiskSeverity::Low, "OS missing patches"),
+    ];
+    checks.into_iter()
+        .filter(|(failed, ..)| *failed)
+        .map(|(_, signal_type, severity, description)| RiskSignal {
+            signal_type,
+            severity,
+            description: description.to_string(),
+            detected_at: posture.last_checked,
+        })
+        .collect()
+}
+
+fn severity_weight(severity: RiskSeverity) -> f64 {
+    match severity {
+        RiskSeverity::Low => 5.0,
+        RiskSeverity::Medium => 15.0,
+        RiskSeverity::High => 30.0,
+        RiskSeverity::Critical => 50.0,
+    }
+}
+
+/// Highest trust a session may keep at a risk score
+fn trust_ceiling(risk: f64) -> TrustLevel {
+    match risk {
+        r if r < 25.0 => TrustLevel::Full,
+        r if r < 50.0 => TrustLevel::High,
+        r if r < 75.0 => TrustLevel::Medium,
+        r if r < 90.0 => TrustLevel::Low,
+        _ => TrustLevel::Untrusted,
+    }
+}
+
+fn same_signal(a: &RiskSignal, b: &RiskSignal) -> bool {
+    a.signal_type == b.signal_type && a.severity == b.severity && a.description == b.description
+}
+
+fn action_details(signals: &[RiskSignal]) -> HashMap<String, String> {
+    let mut details = HashMap::new();
+    let descriptions: Vec<&str> = signals.iter().map(|s| s.description.as_str()).collect();
+    details.insert("signals".to_string(), descriptions.join("; "));
+    details
+}
+
+/// Step-up that answers the signals: device compromise needs the device
+/// re-attested, credential compromise a phishing-resistant factor
+fn step_up_for(signals: &[RiskSignal]) -> ChallengeType {
+    let device = signals.iter().any(|s| matches!(
+        s.signal_type,
+        RiskSignalType::NewDevice | RiskSignalType::MalwareDetected
+    ));
+    let credential = signals.iter().any(|s| matches!(
+        s.signal_type,
+        RiskSignalType::CompromisedCredential | RiskSignalType::BruteForceAttempt | RiskSignalType::ImpossibleTravel
+    ));
+    if device {
+        ChallengeType::DeviceAttestation
+    } else if credential {
+        ChallengeType::Biometric
+    } else {
+        ChallengeType::Mfa
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EvaluationResult {
     pub session_id: String,
@@ -195,6 +417,8 @@ pub struct EvaluationResult {
 pub enum EvaluationAction {
     Continue,
     RequireReauth,
+    /// Inline step-up challenge at the next request
+    StepUp,
     Suspend,
     Revoke,
 }
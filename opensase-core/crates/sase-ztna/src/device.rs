@@ -99,6 +99,31 @@ impl DeviceAssessor {
         self.devices.contains_key(device_id)
     }
     
+    /// Registered device by id
+    pub fn get(&self, device_id: &str) -> Option<Device> {
+        self.devices.get(device_id).map(|r| r.device.clone())
+    }
+    
+    /// User a device is registered to
+    pub fn owner_of(&self, device_id: &str) -> Option<String> {
+        self.devices.get(device_id).map(|r| r.owner_id.clone())
+    }
+    
+    /// Record a certificate issued to a device
+    pub fn add_certificate(&self, device_id: &str, certificate: DeviceCertificate) {
+        if let Some(mut record) = self.devices.get_mut(device_id) {
+            record.device.certificates.retain(|c| c.id != certificate.id);
+            record.device.certificates.push(certificate);
+        }
+    }
+    
+    /// Drop a replaced, revoked or expired certificate
+    pub fn remove_certificate(&self, device_id: &str, certificate_id: &str) {
+        if let Some(mut record) = self.devices.get_mut(device_id) {
+            record.device.certificates.retain(|c| c.id != certificate_id);
+        }
+    }
+    
     /// Assess device trust level
     pub fn assess(&self, device: &Device) -> TrustAssessment {
         let mut score = 0i32;
@@ -29,7 +29,7 @@ struct DeviceRecord {
 
 // IdP connector trait
 pub trait IdpConnector: Send + Sync {
-    fn verify_token(&self, token: &str) -> impl std::future::Future<Output = Option<Identity>> + Send;
+    fn verify_token<'a>(&'a self, token: &'a str) -> std::pin::Pin<Box<dyn std::future::Future<Output = Option<Identity>> + Send + 'a>>;
     fn provider_name(&self) -> &str;
 }
 
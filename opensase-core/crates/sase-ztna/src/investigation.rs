@@ -0,0 +1,827 @@
+//! Insider Investigations
+//!
+//! Per-identity activity reports. DNS and web telemetry arrive keyed by
+//! client address; ZTNA session mappings attribute each record to the user
+//! holding that address at the time. Reports join that telemetry with file
+//! uploads and DLP alerts from the activity logger, are restricted to
+//! investigation roles, and every request is written to the audit trail.
+
+use crate::activity::{ActivityEventType, ActivityLogger, DlpAction, DlpAlertType, DlpSeverity};
+use crate::audit::AuditLogger;
+use crate::Session;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Per-user activity report generator
+pub struct ActivityReporter {
+    config: InvestigationConfig,
+    audit: Arc<AuditLogger>,
+    activity: Option<Arc<ActivityLogger>>,
+    /// Session mappings by client address, oldest first
+    mappings: dashmap::DashMap<IpAddr, Vec<SessionMapping>>,
+    /// Session mappings by session id
+    sessions: dashmap::DashMap<String, SessionMapping>,
+    /// Attributed DNS queries by user
+    dns: dashmap::DashMap<String, VecDeque<DnsQueryRecord>>,
+    /// Attributed web requests by user
+    web: dashmap::DashMap<String, VecDeque<WebRequestRecord>>,
+    /// Telemetry with no session mapping at its timestamp
+    unattributed: AtomicU64,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvestigationConfig {
+    /// Roles allowed to generate reports
+    pub allowed_roles: Vec<String>,
+    /// Longest report window
+    pub max_window: Duration,
+    /// How long attributed telemetry is kept
+    pub retention: Duration,
+    /// Entries in top-N lists
+    pub top_n: usize,
+}
+
+impl Default for InvestigationConfig {
+    fn default() -> Self {
+        Self {
+            allowed_roles: vec![
+                "investigator".to_string(),
+                "insider_risk_analyst".to_string(),
+            ],
+            max_window: Duration::days(90),
+            retention: Duration::days(90),
+            top_n: 25,
+        }
+    }
+}
+
+/// Client address assigned to a ZTNA session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMapping {
+    pub session_id: String,
+    pub user_id: String,
+    pub device_id: String,
+    pub client_ip: IpAddr,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl SessionMapping {
+    fn covers(&self, at: DateTime<Utc>) -> bool {
+        self.started_at <= at && self.ended_at.map(|end| at <= end).unwrap_or(true)
+    }
+    
+    fn overlaps(&self, window: &ReportWindow) -> bool {
+        self.started_at <= window.to && self.ended_at.map(|end| end >= window.from).unwrap_or(true)
+    }
+}
+
+/// DNS query seen by the resolver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsQueryRecord {
+    pub timestamp: DateTime<Utc>,
+    pub client_ip: IpAddr,
+    /// Set by the reporter when not known at the source
+    pub session_id: Option<String>,
+    pub domain: String,
+    pub query_type: String,
+    pub blocked: bool,
+}
+
+/// Web request seen by the secure web gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRequestRecord {
+    pub timestamp: DateTime<Utc>,
+    pub client_ip: IpAddr,
+    /// Set by the reporter when not known at the source
+    pub session_id: Option<String>,
+    pub host: String,
+    pub url: String,
+    pub category: String,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub blocked: bool,
+}
+
+/// Caller requesting a report
+#[derive(Debug, Clone)]
+pub struct Investigator {
+    pub id: String,
+    pub roles: Vec<String>,
+    /// Case or ticket the report is for
+    pub case_id: Option<String>,
+}
+
+/// Time range covered by a report
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReportWindow {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl ReportWindow {
+    pub fn new(from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        Self { from, to }
+    }
+    
+    /// Window ending now
+    pub fn last(duration: Duration) -> Self {
+        let to = Utc::now();
+        Self { from: to - duration, to }
+    }
+    
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from <= at && at <= self.to
+    }
+}
+
+/// Activity report for one user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityReport {
+    pub id: String,
+    pub user_id: String,
+    pub requested_by: String,
+    pub case_id: Option<String>,
+    pub window: ReportWindow,
+    pub generated_at: DateTime<Utc>,
+    pub sessions: Vec<SessionMapping>,
+    pub dns: DnsActivity,
+    pub categories: Vec<CategoryActivity>,
+    pub uploads: Vec<UploadVolume>,
+    pub dlp_incidents: Vec<DlpIncident>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DnsActivity {
+    pub total_queries: u64,
+    pub blocked_queries: u64,
+    pub unique_domains: usize,
+    pub top_domains: Vec<DomainCount>,
+    pub blocked_domains: Vec<DomainCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainCount {
+    pub domain: String,
+    pub queries: u64,
+}
+
+/// Web activity in one URL category
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryActivity {
+    pub category: String,
+    pub requests: u64,
+    pub blocked: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub hosts: usize,
+}
+
+/// Data uploaded to one destination
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UploadVolume {
+    pub destination: String,
+    pub bytes: u64,
+    pub transfers: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DlpIncident {
+    pub id: String,
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub alert_type: DlpAlertType,
+    pub severity: DlpSeverity,
+    pub action_taken: DlpAction,
+    pub pattern_matched: String,
+}
+
+impl ActivityReporter {
+    pub fn new(audit: Arc<AuditLogger>) -> Self {
+        Self {
+            config: InvestigationConfig::default(),
+            audit,
+            activity: None,
+            mappings: dashmap::DashMap::new(),
+            sessions: dashmap::DashMap::new(),
+            dns: dashmap::DashMap::new(),
+            web: dashmap::DashMap::new(),
+            unattributed: AtomicU64::new(0),
+        }
+    }
+    
+    pub fn with_config(mut self, config: InvestigationConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// Join file uploads and DLP alerts from the activity logger
+    pub fn with_activity_logger(mut self, activity: Arc<ActivityLogger>) -> Self {
+        self.activity = Some(activity);
+        self
+    }
+    
+    /// Record the client address a session was established from
+    pub fn map_session(&self, session: &Session, client_ip: IpAddr) {
+        if self.sessions.contains_key(&session.id) {
+            return;
+        }
+        
+        let mapping = SessionMapping {
+            session_id: session.id.clone(),
+            user_id: session.identity.user_id.clone(),
+            device_id: session.device.id.clone(),
+            client_ip,
+            started_at: session.created_at,
+            ended_at: None,
+        };
+        
+        // A new session on the same address closes any open one
+        let closed: Vec<String> = {
+            let mut by_ip = self.mappings.entry(client_ip).or_default();
+            let closed = by_ip.iter_mut()
+                .filter(|m| m.ended_at.is_none())
+                .map(|open| {
+                    open.ended_at = Some(mapping.started_at);
+                    open.session_id.clone()
+                })
+                .collect();
+            by_ip.push(mapping.clone());
+            closed
+        };
+        for session_id in closed {
+            if let Some(mut open) = self.sessions.get_mut(&session_id) {
+                open.ended_at = Some(mapping.started_at);
+            }
+        }
+        
+        self.sessions.insert(mapping.session_id.clone(), mapping);
+    }
+    
+    /// Close a session mapping
+    pub fn end_session(&self, session_id: &str) {
+        let now = Utc::now();
+        let client_ip = {
+            let Some(mut mapping) = self.sessions.get_mut(session_id) else {
+                return;
+            };
+            if mapping.ended_at.is_some() {
+                return;
+            }
+            mapping.ended_at = Some(now);
+            mapping.client_ip
+        };
+        
+        if let Some(mut by_ip) = self.mappings.get_mut(&client_ip) {
+            if let Some(m) = by_ip.iter_mut().find(|m| m.session_id == session_id) {
+                m.ended_at = Some(now);
+            }
+        }
+    }
+    
+    /// Session holding `client_ip` at `at`
+    pub fn attribute(&self, client_ip: IpAddr, at: DateTime<Utc>) -> Option<SessionMapping> {
+        self.mappings.get(&client_ip)?
+            .iter()
+            .rev()
+            .find(|m| m.covers(at))
+            .cloned()
+    }
+    
+    /// Attribute and store a DNS query; false if no session held the address
+    pub fn record_dns(&self, mut record: DnsQueryRecord) -> bool {
+        let Some(mapping) = self.resolve(record.session_id.as_deref(), record.client_ip, record.timestamp) else {
+            self.unattributed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+        
+        record.session_id = Some(mapping.session_id);
+        record.domain = record.domain.trim_end_matches('.').to_lowercase();
+        
+        let cutoff = Utc::now() - self.config.retention;
+        let mut queries = self.dns.entry(mapping.user_id).or_default();
+        while queries.front().map(|q| q.timestamp < cutoff).unwrap_or(false) {
+            queries.pop_front();
+        }
+        queries.push_back(record);
+        true
+    }
+    
+    /// Attribute and store a web request; false if no session held the address
+    pub fn record_web(&self, mut record: WebRequestRecord) -> bool {
+        let Some(mapping) = self.resolve(record.session_id.as_deref(), record.client_ip, record.timestamp) else {
+            self.unattributed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+        
+        record.session_id = Some(mapping.session_id);
+        record.host = record.host.to_lowercase();
+        
+        let cutoff = Utc::now() - self.config.retention;
+        let mut requests = self.web.entry(mapping.user_id).or_default();
+        while requests.front().map(|r| r.timestamp < cutoff).unwrap_or(false) {
+            requests.pop_front();
+        }
+        requests.push_back(record);
+        true
+    }
+    
+    /// Telemetry records dropped for lack of a session mapping
+    pub fn unattributed(&self) -> u64 {
+        self.unattributed.load(Ordering::Relaxed)
+    }
+    
+    /// Drop telemetry and closed mappings past retention
+    pub fn purge_expired(&self) {
+        let cutoff = Utc::now() - self.config.retention;
+        
+        for mut queries in self.dns.iter_mut() {
+            queries.retain(|q| q.timestamp >= cutoff);
+        }
+        self.dns.retain(|_, queries| !queries.is_empty());
+        for mut requests in self.web.iter_mut() {
+            requests.retain(|r| r.timestamp >= cutoff);
+        }
+        self.web.retain(|_, requests| !requests.is_empty());
+        
+        let expired = |m: &SessionMapping| m.ended_at.map(|end| end < cutoff).unwrap_or(false);
+        self.sessions.retain(|_, m| !expired(m));
+        for mut by_ip in self.mappings.iter_mut() {
+            by_ip.retain(|m| !expired(m));
+        }
+        self.mappings.retain(|_, by_ip| !by_ip.is_empty());
+    }
+    
+    /// Generate a report of `user_id`'s activity. Restricted to
+    /// investigation roles; granted and denied requests are both audited.
+    pub async fn generate(
+        &self,
+        investigator: &Investigator,
+        user_id: &str,
+        window: ReportWindow,
+    ) -> Result<ActivityReport, InvestigationError> {
+        let mut details = HashMap::new();
+        details.insert("window_from".to_string(), window.from.to_rfc3339());
+        details.insert("window_to".to_string(), window.to.to_rfc3339());
+        if let Some(case_id) = &investigator.case_id {
+            details.insert("case_id".to_string(), case_id.clone());
+        }
+        
+        let check = if !self.is_authorized(investigator) {
+            Err(InvestigationError::Forbidden(investigator.id.clone()))
+        } else if window.from >= window.to {
+            Err(InvestigationError::InvalidWindow("window start must precede its end".to_string()))
+        } else if window.to - window.from > self.config.max_window {
+            Err(InvestigationError::InvalidWindow(format!(
+                "window exceeds {} days", self.config.max_window.num_days()
+            )))
+        } else {
+            Ok(())
+        };
+        
+        if let Err(e) = check {
+            details.insert("reason".to_string(), e.to_string());
+            self.audit.log_activity_report(&investigator.id, user_id, false, details).await;
+            return Err(e);
+        }
+        
+        let report = self.build(investigator, user_id, window);
+        
+        details.insert("report_id".to_string(), report.id.clone());
+        details.insert("sessions".to_string(), report.sessions.len().to_string());
+        details.insert("dns_queries".to_string(), report.dns.total_queries.to_string());
+        details.insert("dlp_incidents".to_string(), report.dlp_incidents.len().to_string());
+        self.audit.log_activity_report(&investigator.id, user_id, true, details).await;
+        
+        tracing::info!(
+            "Activity report {} for user {} generated by {}",
+            report.id, user_id, investigator.id
+        );
+        
+        Ok(report)
+    }
+    
+    fn is_authorized(&self, investigator: &Investigator) -> bool {
+        investigator.roles.iter().any(|r| self.config.allowed_roles.contains(r))
+    }
+    
+    fn resolve(&self, session_id: Option<&str>, client_ip: IpAddr, at: DateTime<Utc>) -> Option<SessionMapping> {
+        match session_id {
+            Some(id) => self.sessions.get(id).map(|m| m.clone()),
+            None => self.attribute(client_ip, at),
+        }
+    }
+    
+    fn build(&self, investigator: &Investigator, user_id: &str, window: ReportWindow) -> ActivityReport {
+        let mut sessions: Vec<SessionMapping> = self.sessions.iter()
+            .filter(|m| m.user_id == user_id && m.overlaps(&window))
+            .map(|m| m.clone())
+            .collect();
+        sessions.sort_by_key(|m| m.started_at);
+        
+        ActivityReport {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            requested_by: investigator.id.clone(),
+            case_id: investigator.case_id.clone(),
+            window,
+            generated_at: Utc::now(),
+            dns: self.dns_activity(user_id, &window),
+            categories: self.category_activity(user_id, &window),
+            uploads: self.upload_volumes(user_id, &sessions, &window),
+            dlp_incidents: self.dlp_incidents(&sessions, &window),
+            sessions,
+        }
+    }
+    
+    fn dns_activity(&self, user_id: &str, window: &ReportWindow) -> DnsActivity {
+        let Some(queries) = self.dns.get(user_id) else {
+            return DnsActivity::default();
+        };
+        
+        let mut activity = DnsActivity::default();
+        let mut counts: HashMap<&str, (u64, u64)> = HashMap::new();
+        for query in queries.iter().filter(|q| window.contains(q.timestamp)) {
+            activity.total_queries += 1;
+            let count = counts.entry(query.domain.as_str()).or_default();
+            count.0 += 1;
+            if query.blocked {
+                activity.blocked_queries += 1;
+                count.1 += 1;
+            }
+        }
+        
+        activity.unique_domains = counts.len();
+        activity.top_domains = top_n(counts.iter().map(|(d, c)| (*d, c.0)), self.config.top_n);
+        activity.blocked_domains = top_n(
+            counts.iter().filter(|(_, c)| c.1 > 0).map(|(d, c)| (*d, c.1)),
+            self.config.top_n,
+        );
+        activity
+    }
+    
+    fn category_activity(&self, user_id: &str, window: &ReportWindow) -> Vec<CategoryActivity> {
+        let Some(requests) = self.web.get(user_id) else {
+            return Vec::new();
+        };
+        
+        let mut by_category: HashMap<&str, (CategoryActivity, std::collections::HashSet<&str>)> = HashMap::new();
+        for request in requests.iter().filter(|r| window.contains(r.timestamp)) {
+            let (activity, hosts) = by_category.entry(request.category.as_str()).or_default();
+            activity.requests += 1;
+            activity.bytes_sent += request.bytes_sent;
+            activity.bytes_received += request.bytes_received;
+            if request.blocked {
+                activity.blocked += 1;
+            }
+            hosts.insert(request.host.as_str());
+        }
+        
+        let mut categories: Vec<CategoryActivity> = by_category.into_iter()
+            .map(|(category, (mut activity, hosts))| {
+                activity.category = category.to_string();
+                activity.hosts = hosts.len();
+                activity
+            })
+            .collect();
+        categories.sort_by_key(|c| std::cmp::Reverse(c.requests));
+        categories
+    }
+    
+    fn upload_volumes(&self, user_id: &str, sessions: &[SessionMapping], window: &ReportWindow) -> Vec<UploadVolume> {
+        let mut by_destination: HashMap<String, UploadVolume> = HashMap::new();
+        
+        if let Some(requests) = self.web.get(user_id) {
+            for request in requests.iter().filter(|r| r.bytes_sent > 0 && !r.blocked && window.contains(r.timestamp)) {
+                let volume = by_destination.entry(request.host.clone()).or_default();
+                volume.bytes += request.bytes_sent;
+                volume.transfers += 1;
+            }
+        }
+        
+        // File uploads through ZTNA tunnels, keyed by the resource written to
+        if let Some(activity) = &self.activity {
+            for session in sessions {
+                for event in activity.get_session_activity(&session.session_id) {
+                    if event.event_type != ActivityEventType::FileUpload || !window.contains(event.timestamp) {
+                        continue;
+                    }
+                    let volume = by_destination.entry(event.resource.clone()).or_default();
+                    volume.bytes += event.bytes_transferred.unwrap_or(0);
+                    volume.transfers += 1;
+                }
+            }
+        }
+        
+        let mut uploads: Vec<UploadVolume> = by_destination.into_iter()
+            .map(|(destination, mut volume)| {
+                volume.destination = destination;
+                volume
+            })
+            .collect();
+        uploads.sort_by_key(|u| std::cmp::Reverse(u.bytes));
+        uploads
+    }
+    
+    fn dlp_incidents(&self, sessions: &[SessionMapping], window: &ReportWindow) -> Vec<DlpIncident> {
+        let Some(activity) = &self.activity else {
+            return Vec::new();
+        };
+        
+        let mut incidents: Vec<DlpIncident> = sessions.iter()
+            .flat_map(|s| activity.get_session_alerts(&s.session_id))
+            .filter(|a| window.contains(a.timestamp))
+            .map(|a| DlpIncident {
+                id: a.id,
+                session_id: a.session_id,
+                timestamp: a.timestamp,
+                alert_type: a.alert_type,
+                severity: a.severity,
+                action_taken: a.action_taken,
+                pattern_matched: a.pattern_matched,
+            })
+            .collect();
+        incidents.sort_by_key(|i| i.timestamp);
+        incidents
+    }
+}
+
+fn top_n<'a>(counts: impl Iterator<Item = (&'a str, u64)>, n: usize) -> Vec<DomainCount> {
+    let mut sorted: Vec<(&str, u64)> = counts.collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    sorted.into_iter()
+        .take(n)
+        .map(|(domain, queries)| DomainCount { domain: domain.to_string(), queries })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum InvestigationError {
+    /// Caller holds no investigation role
+    Forbidden(String),
+    InvalidWindow(String),
+}
+
+impl std::fmt::Display for InvestigationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forbidden(id) => write!(f, "{} is not permitted to generate activity reports", id),
+            Self::InvalidWindow(reason) => write!(f, "Invalid report window: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for InvestigationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::DlpAlert;
+    use crate::audit::{AuditEventType, AuditQuery};
+    use crate::policy::testing::RequestFixture;
+    use crate::{SessionStatus, TrustLevel};
+
+    const LAPTOP: &str = "10.8.0.5";
+    const PHONE: &str = "10.8.0.9";
+
+    fn ago(minutes: i64) -> DateTime<Utc> {
+        Utc::now() - Duration::minutes(minutes)
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn session(id: &str, user_id: &str, created_at: DateTime<Utc>) -> Session {
+        let mut request = RequestFixture::default().build().unwrap();
+        request.identity.user_id = user_id.to_string();
+        Session {
+            id: id.to_string(),
+            identity: request.identity,
+            device: request.device,
+            created_at,
+            last_activity: created_at,
+            expires_at: created_at + Duration::hours(8),
+            trust_level: TrustLevel::High,
+            risk_score: 0.0,
+            active_resources: Default::default(),
+            status: SessionStatus::Active,
+        }
+    }
+
+    fn dns(at: DateTime<Utc>, client_ip: &str, domain: &str, blocked: bool) -> DnsQueryRecord {
+        DnsQueryRecord {
+            timestamp: at,
+            client_ip: ip(client_ip),
+            session_id: None,
+            domain: domain.to_string(),
+            query_type: "A".to_string(),
+            blocked,
+        }
+    }
+
+    fn web(at: DateTime<Utc>, host: &str, category: &str, bytes_sent: u64, blocked: bool) -> WebRequestRecord {
+        WebRequestRecord {
+            timestamp: at,
+            client_ip: ip(LAPTOP),
+            session_id: None,
+            host: host.to_string(),
+            url: format!("https://{}/", host),
+            category: category.to_string(),
+            bytes_sent,
+            bytes_received: 1000,
+            blocked,
+        }
+    }
+
+    fn investigator(roles: &[&str]) -> Investigator {
+        Investigator {
+            id: "inv-7".to_string(),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            case_id: Some("CASE-42".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_addresses_attribute_to_the_session_holding_them() {
+        let reporter = ActivityReporter::new(Arc::new(AuditLogger::new()));
+        reporter.map_session(&session("s1", "alice", ago(120)), ip(LAPTOP));
+        // DHCP hands alice's old address to bob
+        reporter.map_session(&session("s2", "bob", ago(60)), ip(LAPTOP));
+        reporter.map_session(&session("s2", "mallory", ago(30)), ip(PHONE));
+
+        let holder = |address: &str, at| reporter.attribute(ip(address), at).map(|m| m.user_id);
+        assert_eq!(holder(LAPTOP, ago(90)).as_deref(), Some("alice"));
+        assert_eq!(holder(LAPTOP, ago(30)).as_deref(), Some("bob"));
+        assert_eq!(holder(LAPTOP, ago(180)), None);
+        assert_eq!(holder(PHONE, ago(10)), None, "a known session id is not remapped");
+
+        reporter.end_session("s2");
+        assert_eq!(holder(LAPTOP, Utc::now() + Duration::minutes(1)), None);
+        assert_eq!(holder(LAPTOP, ago(30)).as_deref(), Some("bob"));
+    }
+
+    #[test]
+    fn test_telemetry_is_attributed_and_normalized() {
+        let reporter = ActivityReporter::new(Arc::new(AuditLogger::new()));
+        reporter.map_session(&session("s1", "alice", ago(120)), ip(LAPTOP));
+        reporter.map_session(&session("s2", "bob", ago(120)), ip(PHONE));
+
+        assert!(reporter.record_dns(dns(ago(10), LAPTOP, "Drive.Google.COM.", false)));
+        // The source's session id wins over the address
+        let mut tagged = dns(ago(10), LAPTOP, "example.com", false);
+        tagged.session_id = Some("s2".to_string());
+        assert!(reporter.record_dns(tagged));
+        assert!(reporter.record_web(web(ago(10), "WWW.Dropbox.com", "file_sharing", 10, false)));
+
+        assert!(!reporter.record_dns(dns(ago(10), "10.8.0.200", "example.com", false)));
+        assert!(!reporter.record_web(web(ago(180), "example.com", "news", 0, false)));
+        assert_eq!(reporter.unattributed(), 2);
+
+        let alice = reporter.dns.get("alice").unwrap();
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].domain, "drive.google.com");
+        assert_eq!(alice[0].session_id.as_deref(), Some("s1"));
+        assert_eq!(reporter.dns.get("bob").unwrap()[0].domain, "example.com");
+        assert_eq!(reporter.web.get("alice").unwrap()[0].host, "www.dropbox.com");
+    }
+
+    #[tokio::test]
+    async fn test_report_joins_telemetry_uploads_and_dlp() {
+        let activity = Arc::new(ActivityLogger::new());
+        let reporter = ActivityReporter::new(Arc::new(AuditLogger::new()))
+            .with_config(InvestigationConfig { top_n: 2, ..Default::default() })
+            .with_activity_logger(activity.clone());
+        reporter.map_session(&session("s1", "alice", ago(240)), ip(LAPTOP));
+        reporter.map_session(&session("s9", "bob", ago(240)), ip(PHONE));
+
+        for (minutes, domain, blocked) in [
+            (50, "drive.google.com", false),
+            (40, "drive.google.com", false),
+            (30, "drive.google.com", true),
+            (20, "mega.nz", true),
+            (10, "intranet.corp", false),
+            // Before the window
+            (200, "old.example", false),
+        ] {
+            reporter.record_dns(dns(ago(minutes), LAPTOP, domain, blocked));
+        }
+        reporter.record_dns(dns(ago(10), PHONE, "bob.example", false));
+
+        reporter.record_web(web(ago(40), "drive.google.com", "file_sharing", 5_000_000, false));
+        reporter.record_web(web(ago(30), "mega.nz", "file_sharing", 9_000_000, true));
+        reporter.record_web(web(ago(20), "news.example", "news", 0, false));
+        reporter.record_web(web(ago(15), "drive.google.com", "file_sharing", 1_000_000, false));
+
+        activity.log_file_transfer("s1", "fileserver:/finance/payroll.xlsx", true, 2_000_000);
+        activity.log_file_transfer("s1", "fileserver:/finance/budget.xlsx", false, 8_000_000);
+        activity.log_file_transfer("s9", "fileserver:/bob.txt", true, 1);
+        activity.log_dlp_alert(DlpAlert {
+            id: "dlp-1".to_string(),
+            session_id: "s1".to_string(),
+            timestamp: ago(5),
+            alert_type: DlpAlertType::ConfidentialDocument,
+            severity: DlpSeverity::High,
+            pattern_matched: "CONFIDENTIAL".to_string(),
+            content_sample: None,
+            action_taken: DlpAction::Logged,
+        });
+
+        let report = reporter
+            .generate(&investigator(&["investigator"]), "alice", ReportWindow::last(Duration::hours(2)))
+            .await
+            .unwrap();
+
+        assert_eq!(report.sessions.len(), 1);
+        assert_eq!(report.case_id.as_deref(), Some("CASE-42"));
+
+        assert_eq!(report.dns.total_queries, 5);
+        assert_eq!(report.dns.blocked_queries, 2);
+        assert_eq!(report.dns.unique_domains, 3);
+        let top: Vec<(&str, u64)> = report.dns.top_domains.iter().map(|d| (d.domain.as_str(), d.queries)).collect();
+        assert_eq!(top, vec![("drive.google.com", 3), ("intranet.corp", 1)]);
+        let blocked: Vec<(&str, u64)> = report.dns.blocked_domains.iter().map(|d| (d.domain.as_str(), d.queries)).collect();
+        assert_eq!(blocked, vec![("drive.google.com", 1), ("mega.nz", 1)]);
+
+        assert_eq!(report.categories.len(), 2);
+        let sharing = &report.categories[0];
+        assert_eq!((sharing.category.as_str(), sharing.requests, sharing.blocked, sharing.hosts), ("file_sharing", 3, 1, 2));
+        assert_eq!(sharing.bytes_sent, 15_000_000);
+
+        // Blocked uploads and downloads don't count
+        let uploads: Vec<(&str, u64, u64)> = report.uploads.iter()
+            .map(|u| (u.destination.as_str(), u.bytes, u.transfers))
+            .collect();
+        assert_eq!(uploads, vec![
+            ("drive.google.com", 6_000_000, 2),
+            ("fileserver:/finance/payroll.xlsx", 2_000_000, 1),
+        ]);
+
+        assert_eq!(report.dlp_incidents.len(), 1);
+        assert_eq!(report.dlp_incidents[0].alert_type, DlpAlertType::ConfidentialDocument);
+
+        let empty = reporter
+            .generate(&investigator(&["investigator"]), "carol", ReportWindow::last(Duration::hours(2)))
+            .await
+            .unwrap();
+        assert!(empty.sessions.is_empty() && empty.uploads.is_empty());
+        assert_eq!(empty.dns.total_queries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reports_are_restricted_and_audited() {
+        let audit = Arc::new(AuditLogger::new());
+        let reporter = ActivityReporter::new(audit.clone())
+            .with_config(InvestigationConfig { max_window: Duration::days(7), ..Default::default() });
+        let window = ReportWindow::last(Duration::days(1));
+
+        let denied = reporter.generate(&investigator(&["helpdesk"]), "alice", window).await;
+        assert!(matches!(denied, Err(InvestigationError::Forbidden(id)) if id == "inv-7"));
+
+        let analyst = investigator(&["helpdesk", "insider_risk_analyst"]);
+        let inverted = ReportWindow::new(window.to, window.from);
+        assert!(matches!(reporter.generate(&analyst, "alice", inverted).await, Err(InvestigationError::InvalidWindow(_))));
+        let too_long = reporter.generate(&analyst, "alice", ReportWindow::last(Duration::days(8))).await;
+        assert_eq!(too_long.unwrap_err().to_string(), "Invalid report window: window exceeds 7 days");
+
+        let report = reporter.generate(&analyst, "alice", window).await.unwrap();
+
+        let mut events = audit.query(AuditQuery { resource_id: Some("user:alice".to_string()), ..AuditQuery::new() });
+        events.sort_by_key(|e| e.timestamp);
+        let types: Vec<AuditEventType> = events.iter().map(|e| e.event_type).collect();
+        assert_eq!(types, vec![
+            AuditEventType::ActivityReportDenied,
+            AuditEventType::ActivityReportDenied,
+            AuditEventType::ActivityReportDenied,
+            AuditEventType::ActivityReportGenerated,
+        ]);
+        assert_eq!(events[0].details["reason"], "inv-7 is not permitted to generate activity reports");
+        assert!(events.iter().all(|e| e.user_id.as_deref() == Some("inv-7") && e.details["case_id"] == "CASE-42"));
+        assert_eq!(events[3].details["report_id"], report.id);
+    }
+
+    #[test]
+    fn test_purge_drops_expired_telemetry_and_sessions() {
+        let reporter = ActivityReporter::new(Arc::new(AuditLogger::new()))
+            .with_config(InvestigationConfig { retention: Duration::days(1), ..Default::default() });
+        let day = 24 * 60;
+        reporter.map_session(&session("old", "alice", ago(3 * day)), ip(LAPTOP));
+        reporter.map_session(&session("new", "alice", ago(2 * day)), ip(LAPTOP));
+        reporter.record_dns(dns(ago(2 * day), LAPTOP, "stale.example", false));
+        reporter.record_dns(dns(ago(10), LAPTOP, "fresh.example", false));
+        reporter.record_web(web(ago(2 * day), "stale.example", "news", 0, false));
+
+        reporter.purge_expired();
+
+        assert!(!reporter.sessions.contains_key("old"));
+        assert!(reporter.sessions.contains_key("new"), "open sessions are kept");
+        assert_eq!(reporter.attribute(ip(LAPTOP), ago(3 * day - 60)).map(|m| m.session_id), None);
+        let queries: Vec<String> = reporter.dns.get("alice").unwrap().iter().map(|q| q.domain.clone()).collect();
+        assert_eq!(queries, vec!["fresh.example"]);
+        assert!(reporter.web.get("alice").is_none());
+    }
+}
@@ -0,0 +1,1315 @@
+//! Just-in-Time Privileged Access
+//!
+//! Time-boxed elevation for resources whose policy carries
+//! `AccessCondition::RequireApproval`. A user asks for an action on a
+//! resource with a justification; the request is routed through approval
+//! stages (their manager, the resource owner, the SOC) and every stage must
+//! approve before a grant is issued. Each stage is decided by a different
+//! person, so a manager who also owns the resource cannot clear both
+//! stages alone. `ZeroTrustGateway::request_access`
+//! lets approval-gated requests through only while the user holds a live
+//! grant, and sessions opened under a grant are terminated when it lapses
+//! or is revoked.
+//!
+//! Approvers are told through the configured notifiers (Slack, email) with
+//! signed approve/deny links, and every step is written both to the
+//! request's own history and to the audit trail.
+
+mod notify;
+
+pub use notify::{
+    ActionLinks, ApprovalNotifier, EmailNotifier, JitNotification, NotificationKind, NotifyError,
+    SlackNotifier,
+};
+
+use crate::audit::{AuditEventType, AuditLogger};
+use crate::{AccessAction, DataSensitivity, Resource};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct JitConfig {
+    /// Grant length when the requester does not ask for one
+    pub default_duration: Duration,
+    /// Longest grant any request may ask for
+    pub max_duration: Duration,
+    /// Pending requests lapse after this
+    pub approval_timeout: Duration,
+    pub min_justification_len: usize,
+    /// Approval endpoint for links in notifications, e.g.
+    /// `https://ztna.example.com/jit/decide`
+    pub action_base_url: Option<String>,
+}
+
+impl Default for JitConfig {
+    fn default() -> Self {
+        Self {
+            default_duration: Duration::hours(1),
+            max_duration: Duration::hours(8),
+            approval_timeout: Duration::hours(4),
+            min_justification_len: 10,
+            action_base_url: None,
+        }
+    }
+}
+
+// =============================================================================
+// Approver Routing
+// =============================================================================
+
+/// Who approves a stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApproverRole {
+    /// The requester's line manager
+    Manager,
+    ResourceOwner,
+    /// Security operations on-call
+    Soc,
+    User(String),
+    Group(String),
+}
+
+impl ApproverRole {
+    /// Approver named by a `RequireApproval` condition: `manager`, `owner`,
+    /// `soc`, `group:<name>` or a user id
+    pub fn parse(approver: &str) -> Self {
+        let approver = approver.trim();
+        match approver.to_ascii_lowercase().as_str() {
+            "manager" => Self::Manager,
+            "owner" | "resource_owner" => Self::ResourceOwner,
+            "soc" | "security" => Self::Soc,
+            _ => match approver.strip_prefix("group:") {
+                Some(group) => Self::Group(group.to_string()),
+                None => Self::User(approver.to_string()),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ApproverRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Manager => write!(f, "manager"),
+            Self::ResourceOwner => write!(f, "resource owner"),
+            Self::Soc => write!(f, "SOC"),
+            Self::User(id) => write!(f, "user {}", id),
+            Self::Group(name) => write!(f, "group {}", name),
+        }
+    }
+}
+
+/// Lookups used to resolve approver roles to people
+pub trait ApproverDirectory: Send + Sync {
+    fn manager_of(&self, user_id: &str) -> Option<String>;
+    fn resource_owners(&self, resource: &Resource) -> Vec<String>;
+    fn soc_approvers(&self) -> Vec<String>;
+    fn group_members(&self, group: &str) -> Vec<String>;
+    fn email_of(&self, user_id: &str) -> Option<String>;
+}
+
+/// In-memory directory, loaded from configuration or an HR/IdP sync
+#[derive(Default)]
+pub struct StaticDirectory {
+    managers: dashmap::DashMap<String, String>,
+    /// Owners by resource id; the resource's own `owner` is used otherwise
+    owners: dashmap::DashMap<String, Vec<String>>,
+    soc: parking_lot::RwLock<Vec<String>>,
+    groups: dashmap::DashMap<String, Vec<String>>,
+    emails: dashmap::DashMap<String, String>,
+}
+
+impl StaticDirectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_manager(self, user_id: &str, manager_id: &str) -> Self {
+        self.managers.insert(user_id.to_string(), manager_id.to_string());
+        self
+    }
+
+    pub fn with_owners(self, resource_id: &str, owners: Vec<String>) -> Self {
+        self.owners.insert(resource_id.to_string(), owners);
+        self
+    }
+
+    pub fn with_soc(self, approvers: Vec<String>) -> Self {
+        *self.soc.write() = approvers;
+        self
+    }
+
+    pub fn with_group(self, group: &str, members: Vec<String>) -> Self {
+        self.groups.insert(group.to_string(), members);
+        self
+    }
+
+    pub fn with_email(self, user_id: &str, email: &str) -> Self {
+        self.emails.insert(user_id.to_string(), email.to_string());
+        self
+    }
+
+    /// Replace the SOC rota
+    pub fn set_soc(&self, approvers: Vec<String>) {
+        *self.soc.write() = approvers;
+    }
+}
+
+impl ApproverDirectory for StaticDirectory {
+    fn manager_of(&self, user_id: &str) -> Option<String> {
+        self.managers.get(user_id).map(|m| m.clone())
+    }
+
+    fn resource_owners(&self, resource: &Resource) -> Vec<String> {
+        match self.owners.get(&resource.id) {
+            Some(owners) => owners.clone(),
+            None if !resource.owner.is_empty() => vec![resource.owner.clone()],
+            None => Vec::new(),
+        }
+    }
+
+    fn soc_approvers(&self) -> Vec<String> {
+        self.soc.read().clone()
+    }
+
+    fn group_members(&self, group: &str) -> Vec<String> {
+        self.groups.get(group).map(|g| g.clone()).unwrap_or_default()
+    }
+
+    fn email_of(&self, user_id: &str) -> Option<String> {
+        self.emails.get(user_id).map(|e| e.clone())
+    }
+}
+
+/// Approval requirements for one resource, overriding the defaults
+#[derive(Debug, Clone)]
+pub struct ApprovalPolicy {
+    /// Stages in order; each needs one approval from its approvers
+    pub stages: Vec<ApproverRole>,
+    /// Tighter cap than `JitConfig::max_duration`
+    pub max_duration: Option<Duration>,
+}
+
+/// Default stages by sensitivity: the manager always, the owner for
+/// confidential data, and the SOC above that
+fn default_stages(sensitivity: DataSensitivity) -> Vec<ApproverRole> {
+    match sensitivity {
+        DataSensitivity::Public | DataSensitivity::Internal => vec![ApproverRole::Manager],
+        DataSensitivity::Confidential => vec![ApproverRole::Manager, ApproverRole::ResourceOwner],
+        DataSensitivity::Restricted | DataSensitivity::TopSecret => {
+            vec![ApproverRole::Manager, ApproverRole::ResourceOwner, ApproverRole::Soc]
+        }
+    }
+}
+
+/// Whether each stage can still be decided by a different person, none of
+/// them in `taken`
+fn assignable(stages: &[ApprovalStage], taken: &mut Vec<String>) -> bool {
+    let Some((stage, rest)) = stages.split_first() else { return true };
+    for approver in &stage.approvers {
+        if taken.contains(approver) {
+            continue;
+        }
+        taken.push(approver.clone());
+        let found = assignable(rest, taken);
+        taken.pop();
+        if found {
+            return true;
+        }
+    }
+    false
+}
+
+// =============================================================================
+// Requests and Grants
+// =============================================================================
+
+/// What a user asks for
+#[derive(Debug, Clone)]
+pub struct ElevationRequest {
+    pub user_id: String,
+    pub resource: Resource,
+    pub action: AccessAction,
+    pub justification: String,
+    /// Change or incident ticket
+    pub ticket: Option<String>,
+    pub duration: Option<Duration>,
+    /// Approver from the resource's `RequireApproval` condition
+    pub approver: Option<String>,
+}
+
+impl ElevationRequest {
+    pub fn new(user_id: &str, resource: Resource, action: AccessAction, justification: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            resource,
+            action,
+            justification: justification.to_string(),
+            ticket: None,
+            duration: None,
+            approver: None,
+        }
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn with_ticket(mut self, ticket: &str) -> Self {
+        self.ticket = Some(ticket.to_string());
+        self
+    }
+
+    pub fn with_approver(mut self, approver: &str) -> Self {
+        self.approver = Some(approver.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JitStatus {
+    Pending,
+    /// Approved; the grant is live
+    Active,
+    Denied,
+    Cancelled,
+    /// Not decided before the approval timeout
+    TimedOut,
+    /// Grant ran its full length
+    Expired,
+    Revoked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JitRequest {
+    pub id: String,
+    pub user_id: String,
+    pub resource_id: String,
+    pub resource_name: String,
+    pub action: AccessAction,
+    pub justification: String,
+    pub ticket: Option<String>,
+    pub duration_secs: i64,
+    pub status: JitStatus,
+    pub stages: Vec<ApprovalStage>,
+    /// Index of the stage awaiting a decision
+    pub current_stage: usize,
+    pub created_at: DateTime<Utc>,
+    /// Pending requests time out at this point
+    pub decide_by: DateTime<Utc>,
+    pub grant: Option<JitGrant>,
+    pub history: Vec<JitHistoryEntry>,
+}
+
+impl JitRequest {
+    /// People who can decide the current stage
+    pub fn pending_approvers(&self) -> &[String] {
+        match (self.status, self.stages.get(self.current_stage)) {
+            (JitStatus::Pending, Some(stage)) => &stage.approvers,
+            _ => &[],
+        }
+    }
+
+    fn record(&mut self, actor: &str, event: JitEvent, comment: Option<String>) {
+        self.history.push(JitHistoryEntry {
+            at: Utc::now(),
+            actor: actor.to_string(),
+            event,
+            comment,
+        });
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalStage {
+    pub role: ApproverRole,
+    pub approvers: Vec<String>,
+    pub decision: Option<StageDecision>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageDecision {
+    pub approver_id: String,
+    pub approved: bool,
+    pub comment: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+/// Live elevation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JitGrant {
+    pub id: String,
+    pub request_id: String,
+    pub user_id: String,
+    pub resource_id: String,
+    pub action: AccessAction,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Sessions opened under this grant, terminated when it ends
+    pub session_ids: Vec<String>,
+}
+
+impl JitGrant {
+    /// Admin elevation covers every action on the resource
+    pub fn covers(&self, resource_id: &str, action: AccessAction) -> bool {
+        self.resource_id == resource_id
+            && (self.action == action || self.action == AccessAction::Admin)
+    }
+
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JitHistoryEntry {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub event: JitEvent,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JitEvent {
+    Requested,
+    StageApproved,
+    Denied,
+    Cancelled,
+    Granted,
+    SessionBound,
+    TimedOut,
+    Expired,
+    Revoked,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JitStats {
+    pub pending: usize,
+    pub active: usize,
+    pub denied: usize,
+    pub lapsed: usize,
+    pub revoked: usize,
+}
+
+/// Result of an approver's decision
+enum Outcome {
+    NextStage,
+    Granted,
+    Denied,
+}
+
+// =============================================================================
+// JIT Manager
+// =============================================================================
+
+/// Just-in-time access workflow
+pub struct JitManager {
+    config: JitConfig,
+    directory: Arc<dyn ApproverDirectory>,
+    audit: Arc<AuditLogger>,
+    notifiers: Vec<Arc<dyn ApprovalNotifier>>,
+    /// Approval policies by resource id
+    policies: dashmap::DashMap<String, ApprovalPolicy>,
+    requests: dashmap::DashMap<String, JitRequest>,
+    /// Active request ids by (user, resource)
+    active: dashmap::DashMap<(String, String), Vec<String>>,
+    /// Signs approve/deny links
+    link_key: [u8; 32],
+}
+
+impl JitManager {
+    pub fn new(directory: Arc<dyn ApproverDirectory>, audit: Arc<AuditLogger>) -> Self {
+        use rand::RngCore;
+        let mut link_key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut link_key);
+
+        Self {
+            config: JitConfig::default(),
+            directory,
+            audit,
+            notifiers: Vec::new(),
+            policies: dashmap::DashMap::new(),
+            requests: dashmap::DashMap::new(),
+            active: dashmap::DashMap::new(),
+            link_key,
+        }
+    }
+
+    pub fn with_config(mut self, config: JitConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: Arc<dyn ApprovalNotifier>) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+
+    /// Links must survive a restart or be verified on another node
+    pub fn with_link_key(mut self, key: [u8; 32]) -> Self {
+        self.link_key = key;
+        self
+    }
+
+    pub fn set_policy(&self, resource_id: &str, policy: ApprovalPolicy) {
+        self.policies.insert(resource_id.to_string(), policy);
+    }
+
+    pub fn get(&self, request_id: &str) -> Option<JitRequest> {
+        self.requests.get(request_id).map(|r| r.clone())
+    }
+
+    /// Requests waiting on this approver
+    pub fn pending_for(&self, approver_id: &str) -> Vec<JitRequest> {
+        let mut pending: Vec<JitRequest> = self.requests.iter()
+            .filter(|r| r.pending_approvers().iter().any(|a| a == approver_id))
+            .map(|r| r.clone())
+            .collect();
+        pending.sort_by_key(|r| r.created_at);
+        pending
+    }
+
+    pub fn requests_of(&self, user_id: &str) -> Vec<JitRequest> {
+        let mut requests: Vec<JitRequest> = self.requests.iter()
+            .filter(|r| r.user_id == user_id)
+            .map(|r| r.clone())
+            .collect();
+        requests.sort_by_key(|r| r.created_at);
+        requests
+    }
+
+    /// Submit a request and notify the first stage's approvers
+    pub async fn request(&self, elevation: ElevationRequest) -> Result<JitRequest, JitError> {
+        let justification = elevation.justification.trim();
+        if justification.len() < self.config.min_justification_len {
+            return Err(JitError::InvalidRequest(format!(
+                "justification must be at least {} characters", self.config.min_justification_len
+            )));
+        }
+
+        let policy = self.policies.get(&elevation.resource.id).map(|p| p.clone());
+        let max_duration = policy.as_ref()
+            .and_then(|p| p.max_duration)
+            .map(|d| d.min(self.config.max_duration))
+            .unwrap_or(self.config.max_duration);
+        let duration = elevation.duration.unwrap_or(self.config.default_duration).min(max_duration);
+        if duration <= Duration::zero() {
+            return Err(JitError::InvalidRequest("duration must be positive".to_string()));
+        }
+        if elevation.duration.map(|d| d > max_duration).unwrap_or(false) {
+            return Err(JitError::InvalidRequest(format!(
+                "at most {} minutes may be requested for {}",
+                max_duration.num_minutes(), elevation.resource.name
+            )));
+        }
+
+        if let Some(existing) = self.requests.iter().find(|r| {
+            r.user_id == elevation.user_id
+                && r.resource_id == elevation.resource.id
+                && r.action == elevation.action
+                && matches!(r.status, JitStatus::Pending | JitStatus::Active)
+        }) {
+            return Err(JitError::Duplicate(existing.id.clone()));
+        }
+
+        let mut roles = policy.map(|p| p.stages)
+            .unwrap_or_else(|| default_stages(elevation.resource.sensitivity));
+        if let Some(role) = elevation.approver.as_deref().map(ApproverRole::parse) {
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
+        let stages = roles.into_iter()
+            .map(|role| {
+                let approvers = self.resolve(&role, &elevation);
+                if approvers.is_empty() {
+                    return Err(JitError::NoApprovers(role));
+                }
+                Ok(ApprovalStage { role, approvers, decision: None })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !assignable(&stages, &mut Vec::new()) {
+            return Err(JitError::NoIndependentApprovers);
+        }
+
+        let now = Utc::now();
+        let mut request = JitRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: elevation.user_id.clone(),
+            resource_id: elevation.resource.id.clone(),
+            resource_name: elevation.resource.name.clone(),
+            action: elevation.action,
+            justification: justification.to_string(),
+            ticket: elevation.ticket.clone(),
+            duration_secs: duration.num_seconds(),
+            status: JitStatus::Pending,
+            stages,
+            current_stage: 0,
+            created_at: now,
+            decide_by: now + self.config.approval_timeout,
+            grant: None,
+            history: Vec::new(),
+        };
+        request.record(&elevation.user_id, JitEvent::Requested, Some(request.justification.clone()));
+        self.requests.insert(request.id.clone(), request.clone());
+
+        tracing::info!(
+            "JIT request {} by {} for {:?} on {} ({} stages)",
+            request.id, request.user_id, request.action, request.resource_id, request.stages.len()
+        );
+        self.audit_event(AuditEventType::JitRequested, &request.user_id, &request, None, None).await;
+        self.notify_approvers(&request).await;
+
+        Ok(request)
+    }
+
+    fn resolve(&self, role: &ApproverRole, elevation: &ElevationRequest) -> Vec<String> {
+        let mut approvers = match role {
+            ApproverRole::Manager => self.directory.manager_of(&elevation.user_id).into_iter().collect(),
+            ApproverRole::ResourceOwner => self.directory.resource_owners(&elevation.resource),
+            ApproverRole::Soc => self.directory.soc_approvers(),
+            ApproverRole::User(id) => vec![id.clone()],
+            ApproverRole::Group(group) => self.directory.group_members(group),
+        };
+        // Nobody approves their own elevation
+        approvers.retain(|a| *a != elevation.user_id);
+        approvers.sort();
+        approvers.dedup();
+        approvers
+    }
+
+    pub async fn approve(
+        &self,
+        request_id: &str,
+        approver_id: &str,
+        comment: Option<String>,
+    ) -> Result<JitRequest, JitError> {
+        self.decide(request_id, approver_id, true, comment).await
+    }
+
+    pub async fn deny(
+        &self,
+        request_id: &str,
+        approver_id: &str,
+        comment: Option<String>,
+    ) -> Result<JitRequest, JitError> {
+        self.decide(request_id, approver_id, false, comment).await
+    }
+
+    /// Decide from a signed link in a notification
+    pub async fn decide_with_token(&self, token: &str) -> Result<JitRequest, JitError> {
+        let (request_id, approver_id, approved) = self.verify_token(token)?;
+        self.decide(&request_id, &approver_id, approved, Some("via approval link".to_string())).await
+    }
+
+    async fn decide(
+        &self,
+        request_id: &str,
+        approver_id: &str,
+        approved: bool,
+        comment: Option<String>,
+    ) -> Result<JitRequest, JitError> {
+        let now = Utc::now();
+        let (outcome, request) = {
+            let mut request = self.requests.get_mut(request_id)
+                .ok_or_else(|| JitError::NotFound(request_id.to_string()))?;
+            if request.status != JitStatus::Pending {
+                return Err(JitError::NotPending(request.status));
+            }
+            if now > request.decide_by {
+                // Left for `expire_due` to close out and notify
+                return Err(JitError::NotPending(JitStatus::TimedOut));
+            }
+            if approver_id == request.user_id {
+                return Err(JitError::SelfApproval);
+            }
+            let stage = request.current_stage;
+            if request.stages[..stage].iter().any(|s| s.decision.as_ref().is_some_and(|d| d.approver_id == approver_id)) {
+                return Err(JitError::AlreadyDecided(approver_id.to_string()));
+            }
+            if !request.pending_approvers().iter().any(|a| a == approver_id) {
+                return Err(JitError::NotAnApprover(approver_id.to_string()));
+            }
+            // Approving must leave someone else for every later stage
+            if approved && !assignable(&request.stages[stage + 1..], &mut vec![approver_id.to_string()]) {
+                return Err(JitError::NoIndependentApprovers);
+            }
+
+            request.stages[stage].decision = Some(StageDecision {
+                approver_id: approver_id.to_string(),
+                approved,
+                comment: comment.clone(),
+                decided_at: now,
+            });
+
+            let outcome = if !approved {
+                request.status = JitStatus::Denied;
+                request.record(approver_id, JitEvent::Denied, comment);
+                Outcome::Denied
+            } else {
+                request.record(approver_id, JitEvent::StageApproved, comment);
+                for later in &mut request.stages[stage + 1..] {
+                    later.approvers.retain(|a| a != approver_id);
+                }
+                request.current_stage += 1;
+                if request.current_stage < request.stages.len() {
+                    Outcome::NextStage
+                } else {
+                    let grant = JitGrant {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        request_id: request.id.clone(),
+                        user_id: request.user_id.clone(),
+                        resource_id: request.resource_id.clone(),
+                        action: request.action,
+                        granted_at: now,
+                        expires_at: now + Duration::seconds(request.duration_secs),
+                        session_ids: Vec::new(),
+                    };
+                    request.grant = Some(grant);
+                    request.status = JitStatus::Active;
+                    request.record("system", JitEvent::Granted, None);
+                    Outcome::Granted
+                }
+            };
+            (outcome, request.clone())
+        };
+
+        match outcome {
+            Outcome::NextStage => {
+                self.audit_event(AuditEventType::JitApproved, approver_id, &request, None, None).await;
+                self.notify_approvers(&request).await;
+            }
+            Outcome::Denied => {
+                tracing::info!("JIT request {} denied by {}", request.id, approver_id);
+                self.audit_event(AuditEventType::JitDenied, approver_id, &request, None, None).await;
+                self.notify_requester(NotificationKind::Denied, &request).await;
+            }
+            Outcome::Granted => {
+                self.active.entry((request.user_id.clone(), request.resource_id.clone()))
+                    .or_default()
+                    .push(request.id.clone());
+                let expires_at = request.grant.as_ref().map(|g| g.expires_at.to_rfc3339());
+                tracing::info!(
+                    "JIT grant for {} on {} until {}",
+                    request.user_id, request.resource_id, expires_at.as_deref().unwrap_or("-")
+                );
+                self.audit_event(AuditEventType::JitApproved, approver_id, &request, None, None).await;
+                self.audit_event(AuditEventType::JitGranted, "system", &request, None, None).await;
+                self.notify_requester(NotificationKind::Granted, &request).await;
+            }
+        }
+
+        Ok(request)
+    }
+
+    /// Withdraw a pending request
+    pub async fn cancel(&self, request_id: &str, user_id: &str) -> Result<JitRequest, JitError> {
+        let request = {
+            let mut request = self.requests.get_mut(request_id)
+                .ok_or_else(|| JitError::NotFound(request_id.to_string()))?;
+            if request.user_id != user_id {
+                return Err(JitError::NotRequester(user_id.to_string()));
+            }
+            if request.status != JitStatus::Pending {
+                return Err(JitError::NotPending(request.status));
+            }
+            request.status = JitStatus::Cancelled;
+            request.record(user_id, JitEvent::Cancelled, None);
+            request.clone()
+        };
+
+        self.audit_event(AuditEventType::JitCancelled, user_id, &request, None, None).await;
+        self.notify(NotificationKind::Cancelled, &request, request.stages[request.current_stage].approvers.clone()).await;
+        Ok(request)
+    }
+
+    /// Live grant covering this access, if any
+    pub fn active_grant(&self, user_id: &str, resource_id: &str, action: AccessAction) -> Option<JitGrant> {
+        let now = Utc::now();
+        let ids = self.active.get(&(user_id.to_string(), resource_id.to_string()))?.clone();
+        ids.iter()
+            .filter_map(|id| self.requests.get(id).and_then(|r| r.grant.clone()))
+            .filter(|g| g.is_live(now) && g.covers(resource_id, action))
+            .max_by_key(|g| g.expires_at)
+    }
+
+    /// Tie a session to a grant so it ends with it
+    pub async fn bind_session(&self, grant: &JitGrant, session_id: &str) {
+        let request = {
+            let Some(mut request) = self.requests.get_mut(&grant.request_id) else { return };
+            let Some(live) = request.grant.as_mut() else { return };
+            if live.session_ids.iter().any(|s| s == session_id) {
+                return;
+            }
+            live.session_ids.push(session_id.to_string());
+            request.record(&grant.user_id, JitEvent::SessionBound, Some(session_id.to_string()));
+            request.clone()
+        };
+        self.audit_event(AuditEventType::JitSessionBound, &grant.user_id, &request, Some(session_id), None).await;
+    }
+
+    /// End a live grant early. Returns the grant so the caller can
+    /// terminate its sessions.
+    pub async fn revoke(&self, request_id: &str, actor_id: &str, reason: &str) -> Result<JitGrant, JitError> {
+        let (request, grant) = {
+            let mut request = self.requests.get_mut(request_id)
+                .ok_or_else(|| JitError::NotFound(request_id.to_string()))?;
+            if request.status != JitStatus::Active {
+                return Err(JitError::NotActive(request.status));
+            }
+            let grant = request.grant.clone().ok_or(JitError::NotActive(request.status))?;
+            request.status = JitStatus::Revoked;
+            request.record(actor_id, JitEvent::Revoked, Some(reason.to_string()));
+            (request.clone(), grant)
+        };
+        self.remove_active(&request);
+
+        tracing::warn!("JIT grant {} for {} revoked by {}: {}", grant.id, grant.user_id, actor_id, reason);
+        self.audit_event(AuditEventType::JitRevoked, actor_id, &request, None, Some(reason)).await;
+        self.notify_requester(NotificationKind::Revoked, &request).await;
+        Ok(grant)
+    }
+
+    /// Time out undecided requests and end lapsed grants. Returns the
+    /// sessions opened under lapsed grants, which the caller terminates.
+    pub async fn expire_due(&self) -> Vec<String> {
+        let now = Utc::now();
+        let mut timed_out = Vec::new();
+        let mut expired = Vec::new();
+
+        for mut request in self.requests.iter_mut() {
+            match request.status {
+                JitStatus::Pending if now > request.decide_by => {
+                    request.status = JitStatus::TimedOut;
+                    request.record("system", JitEvent::TimedOut, None);
+                    timed_out.push(request.clone());
+                }
+                JitStatus::Active if request.grant.as_ref().map(|g| !g.is_live(now)).unwrap_or(true) => {
+                    request.status = JitStatus::Expired;
+                    request.record("system", JitEvent::Expired, None);
+                    expired.push(request.clone());
+                }
+                _ => {}
+            }
+        }
+
+        for request in &timed_out {
+            tracing::info!("JIT request {} timed out awaiting approval", request.id);
+            self.audit_event(AuditEventType::JitTimedOut, "system", request, None, None).await;
+            self.notify_requester(NotificationKind::TimedOut, request).await;
+        }
+
+        let mut sessions = Vec::new();
+        for request in &expired {
+            self.remove_active(request);
+            if let Some(grant) = &request.grant {
+                sessions.extend(grant.session_ids.iter().cloned());
+            }
+            tracing::info!("JIT grant for {} on {} expired", request.user_id, request.resource_id);
+            self.audit_event(AuditEventType::JitExpired, "system", request, None, None).await;
+            self.notify_requester(NotificationKind::Expired, request).await;
+        }
+        sessions
+    }
+
+    fn remove_active(&self, request: &JitRequest) {
+        let key = (request.user_id.clone(), request.resource_id.clone());
+        if let Some(mut ids) = self.active.get_mut(&key) {
+            ids.retain(|id| *id != request.id);
+        }
+        self.active.remove_if(&key, |_, ids| ids.is_empty());
+    }
+
+    pub fn stats(&self) -> JitStats {
+        let mut stats = JitStats::default();
+        for request in self.requests.iter() {
+            match request.status {
+                JitStatus::Pending => stats.pending += 1,
+                JitStatus::Active => stats.active += 1,
+                JitStatus::Denied => stats.denied += 1,
+                JitStatus::TimedOut | JitStatus::Expired | JitStatus::Cancelled => stats.lapsed += 1,
+                JitStatus::Revoked => stats.revoked += 1,
+            }
+        }
+        stats
+    }
+
+    // -------------------------------------------------------------------------
+    // Approval links
+    // -------------------------------------------------------------------------
+
+    /// `base64url(request|approver|a or d|expiry).base64url(HMAC-SHA256)`
+    fn sign_token(&self, request: &JitRequest, approver_id: &str, approved: bool) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let claims = format!(
+            "{}|{}|{}|{}",
+            request.id, approver_id, if approved { "a" } else { "d" }, request.decide_by.timestamp()
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.link_key).expect("HMAC accepts any key length");
+        mac.update(claims.as_bytes());
+        format!("{}.{}", engine.encode(&claims), engine.encode(mac.finalize().into_bytes()))
+    }
+
+    fn verify_token(&self, token: &str) -> Result<(String, String, bool), JitError> {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let (claims, signature) = token.split_once('.').ok_or(JitError::InvalidToken)?;
+        let claims = engine.decode(claims).map_err(|_| JitError::InvalidToken)?;
+        let signature = engine.decode(signature).map_err(|_| JitError::InvalidToken)?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.link_key).expect("HMAC accepts any key length");
+        mac.update(&claims);
+        mac.verify_slice(&signature).map_err(|_| JitError::InvalidToken)?;
+
+        let claims = String::from_utf8(claims).map_err(|_| JitError::InvalidToken)?;
+        let parts: Vec<&str> = claims.split('|').collect();
+        let [request_id, approver_id, decision, expiry] = parts.as_slice() else {
+            return Err(JitError::InvalidToken);
+        };
+        let expiry: i64 = expiry.parse().map_err(|_| JitError::InvalidToken)?;
+        if Utc::now().timestamp() > expiry {
+            return Err(JitError::InvalidToken);
+        }
+        Ok((request_id.to_string(), approver_id.to_string(), *decision == "a"))
+    }
+
+    fn links_for(&self, request: &JitRequest, approver_id: &str) -> Option<ActionLinks> {
+        let base = self.config.action_base_url.as_deref()?.trim_end_matches('/');
+        Some(ActionLinks {
+            approve: format!("{}?token={}", base, self.sign_token(request, approver_id, true)),
+            deny: format!("{}?token={}", base, self.sign_token(request, approver_id, false)),
+        })
+    }
+
+    // -------------------------------------------------------------------------
+    // Notifications and audit
+    // -------------------------------------------------------------------------
+
+    async fn notify_approvers(&self, request: &JitRequest) {
+        let approvers = request.pending_approvers().to_vec();
+        self.notify(NotificationKind::ApprovalRequested, request, approvers).await;
+    }
+
+    async fn notify_requester(&self, kind: NotificationKind, request: &JitRequest) {
+        self.notify(kind, request, vec![request.user_id.clone()]).await;
+    }
+
+    /// Notification failures are logged; they never block the workflow
+    async fn notify(&self, kind: NotificationKind, request: &JitRequest, recipients: Vec<String>) {
+        if self.notifiers.is_empty() || recipients.is_empty() {
+            return;
+        }
+
+        let links = if kind == NotificationKind::ApprovalRequested {
+            recipients.iter()
+                .filter_map(|r| self.links_for(request, r).map(|l| (r.clone(), l)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let notification = JitNotification {
+            kind,
+            request: request.clone(),
+            recipients,
+            links,
+        };
+
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.notify(&notification).await {
+                tracing::warn!(
+                    "{} notification for JIT request {} failed: {}",
+                    notifier.name(), request.id, e
+                );
+            }
+        }
+    }
+
+    async fn audit_event(
+        &self,
+        event_type: AuditEventType,
+        actor_id: &str,
+        request: &JitRequest,
+        session_id: Option<&str>,
+        reason: Option<&str>,
+    ) {
+        let mut details = HashMap::new();
+        details.insert("request_id".to_string(), request.id.clone());
+        details.insert("requester".to_string(), request.user_id.clone());
+        details.insert("status".to_string(), format!("{:?}", request.status));
+        details.insert("justification".to_string(), request.justification.clone());
+        details.insert("duration_secs".to_string(), request.duration_secs.to_string());
+        if let Some(ticket) = &request.ticket {
+            details.insert("ticket".to_string(), ticket.clone());
+        }
+        // Latest decision, for approval and denial events
+        if let Some(stage) = request.stages.iter().rev().find(|s| s.decision.is_some()) {
+            details.insert("stage".to_string(), stage.role.to_string());
+            if let Some(comment) = stage.decision.as_ref().and_then(|d| d.comment.as_ref()) {
+                details.insert("comment".to_string(), comment.clone());
+            }
+        }
+        if let Some(grant) = &request.grant {
+            details.insert("grant_id".to_string(), grant.id.clone());
+            details.insert("expires_at".to_string(), grant.expires_at.to_rfc3339());
+        }
+        if let Some(reason) = reason {
+            details.insert("reason".to_string(), reason.to_string());
+        }
+
+        self.audit.log_jit(
+            event_type,
+            actor_id,
+            &request.resource_id,
+            request.action,
+            session_id,
+            details,
+        ).await;
+    }
+}
+
+#[derive(Debug)]
+pub enum JitError {
+    NotFound(String),
+    InvalidRequest(String),
+    /// A pending or active request for the same access exists
+    Duplicate(String),
+    /// Nobody resolves for a required stage
+    NoApprovers(ApproverRole),
+    NotAnApprover(String),
+    SelfApproval,
+    /// The approver already decided an earlier stage of the request
+    AlreadyDecided(String),
+    /// Stages cannot each be decided by a different approver
+    NoIndependentApprovers,
+    NotRequester(String),
+    NotPending(JitStatus),
+    NotActive(JitStatus),
+    InvalidToken,
+}
+
+impl std::fmt::Display for JitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "JIT request not found: {}", id),
+            Self::InvalidRequest(reason) => write!(f, "Invalid JIT request: {}", reason),
+            Self::Duplicate(id) => write!(f, "An open JIT request already covers this access: {}", id),
+            Self::NoApprovers(role) => write!(f, "No approvers found for {}", role),
+            Self::NotAnApprover(id) => write!(f, "{} is not an approver for this stage", id),
+            Self::SelfApproval => write!(f, "Requesters cannot approve their own elevation"),
+            Self::AlreadyDecided(id) => write!(f, "{} already decided an earlier stage of this request", id),
+            Self::NoIndependentApprovers => write!(f, "Each approval stage needs a different approver"),
+            Self::NotRequester(id) => write!(f, "{} did not make this request", id),
+            Self::NotPending(status) => write!(f, "JIT request is not pending ({:?})", status),
+            Self::NotActive(status) => write!(f, "JIT grant is not active ({:?})", status),
+            Self::InvalidToken => write!(f, "Invalid or expired approval link"),
+        }
+    }
+}
+
+impl std::error::Error for JitError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditQuery;
+    use crate::ResourceType;
+
+    fn resource(sensitivity: DataSensitivity) -> Resource {
+        Resource {
+            id: "db-prod".to_string(),
+            name: "Production database".to_string(),
+            resource_type: ResourceType::Database,
+            sensitivity,
+            owner: String::new(),
+            tags: HashMap::new(),
+            access_policy: None,
+        }
+    }
+
+    fn manager(directory: StaticDirectory) -> (JitManager, Arc<AuditLogger>) {
+        let audit = Arc::new(AuditLogger::new());
+        (JitManager::new(Arc::new(directory), audit.clone()), audit)
+    }
+
+    fn elevation(sensitivity: DataSensitivity) -> ElevationRequest {
+        ElevationRequest::new("alice", resource(sensitivity), AccessAction::Admin, "INC-42 failover repair")
+    }
+
+    #[tokio::test]
+    async fn test_stages_progress_to_grant() {
+        let directory = StaticDirectory::new()
+            .with_manager("alice", "bob")
+            .with_owners("db-prod", vec!["carol".to_string()])
+            .with_soc(vec!["dave".to_string()]);
+        let (jit, audit) = manager(directory);
+
+        let request = jit.request(elevation(DataSensitivity::Restricted)).await.unwrap();
+        assert_eq!(request.stages.len(), 3);
+        assert_eq!(request.pending_approvers(), ["bob".to_string()]);
+        assert_eq!(jit.pending_for("bob").len(), 1);
+        assert!(jit.pending_for("carol").is_empty());
+
+        let request = jit.approve(&request.id, "bob", None).await.unwrap();
+        assert_eq!(request.status, JitStatus::Pending);
+        assert_eq!(request.pending_approvers(), ["carol".to_string()]);
+        assert!(matches!(
+            jit.approve(&request.id, "dave", None).await,
+            Err(JitError::NotAnApprover(_))
+        ));
+
+        jit.approve(&request.id, "carol", None).await.unwrap();
+        let request = jit.approve(&request.id, "dave", Some("on call".to_string())).await.unwrap();
+        assert_eq!(request.status, JitStatus::Active);
+        let grant = request.grant.clone().unwrap();
+        assert_eq!(grant.expires_at - grant.granted_at, Duration::hours(1));
+
+        // Admin elevation covers reads too
+        assert!(jit.active_grant("alice", "db-prod", AccessAction::Read).is_some());
+        assert!(jit.active_grant("alice", "other", AccessAction::Read).is_none());
+
+        let granted = audit.query(AuditQuery {
+            event_types: vec![AuditEventType::JitGranted],
+            ..AuditQuery::new()
+        });
+        assert_eq!(granted.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_denial_ends_request() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+
+        let request = jit.deny(&request.id, "bob", Some("no ticket".to_string())).await.unwrap();
+        assert_eq!(request.status, JitStatus::Denied);
+        assert!(request.grant.is_none());
+        assert!(matches!(
+            jit.approve(&request.id, "bob", None).await,
+            Err(JitError::NotPending(JitStatus::Denied))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_requester_cannot_approve() {
+        let directory = StaticDirectory::new()
+            .with_manager("alice", "bob")
+            .with_group("dbas", vec!["alice".to_string(), "erin".to_string()]);
+        let (jit, _) = manager(directory);
+
+        let request = jit.request(elevation(DataSensitivity::Internal).with_approver("group:dbas"))
+            .await
+            .unwrap();
+        // Requesters are never listed as their own approvers
+        assert_eq!(request.stages[1].approvers, vec!["erin".to_string()]);
+        assert!(matches!(
+            jit.approve(&request.id, "alice", None).await,
+            Err(JitError::SelfApproval)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_one_person_cannot_clear_two_stages() {
+        // The manager also owns the resource, alongside a second owner
+        let directory = StaticDirectory::new()
+            .with_manager("alice", "bob")
+            .with_owners("db-prod", vec!["bob".to_string(), "carol".to_string()]);
+        let (jit, _) = manager(directory);
+
+        let request = jit.request(elevation(DataSensitivity::Confidential)).await.unwrap();
+        let request = jit.approve(&request.id, "bob", None).await.unwrap();
+        assert_eq!(request.pending_approvers(), ["carol".to_string()]);
+        assert!(matches!(
+            jit.approve(&request.id, "bob", None).await,
+            Err(JitError::AlreadyDecided(_))
+        ));
+
+        let request = jit.approve(&request.id, "carol", None).await.unwrap();
+        assert_eq!(request.status, JitStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn test_sole_approver_of_two_stages_rejected() {
+        let directory = StaticDirectory::new()
+            .with_manager("alice", "bob")
+            .with_owners("db-prod", vec!["bob".to_string()]);
+        let (jit, _) = manager(directory);
+
+        assert!(matches!(
+            jit.request(elevation(DataSensitivity::Confidential)).await,
+            Err(JitError::NoIndependentApprovers)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_approval_that_strands_later_stage_rejected() {
+        // Either SOC member can clear the first stage, but only erin can
+        // clear the second, so erin's first-stage approval is refused
+        let (jit, _) = manager(StaticDirectory::new().with_soc(vec!["dave".to_string(), "erin".to_string()]));
+        jit.set_policy("db-prod", ApprovalPolicy {
+            stages: vec![ApproverRole::Soc, ApproverRole::User("erin".to_string())],
+            max_duration: None,
+        });
+
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+        assert!(matches!(
+            jit.approve(&request.id, "erin", None).await,
+            Err(JitError::NoIndependentApprovers)
+        ));
+        jit.approve(&request.id, "dave", None).await.unwrap();
+        let request = jit.approve(&request.id, "erin", None).await.unwrap();
+        assert_eq!(request.status, JitStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_requests() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+
+        let short = ElevationRequest::new("alice", resource(DataSensitivity::Internal), AccessAction::Read, "fix");
+        assert!(matches!(jit.request(short).await, Err(JitError::InvalidRequest(_))));
+
+        let long = elevation(DataSensitivity::Internal).with_duration(Duration::hours(9));
+        assert!(matches!(jit.request(long).await, Err(JitError::InvalidRequest(_))));
+
+        let orphan = ElevationRequest::new("zed", resource(DataSensitivity::Internal), AccessAction::Read, "INC-42 failover repair");
+        assert!(matches!(jit.request(orphan).await, Err(JitError::NoApprovers(ApproverRole::Manager))));
+
+        let first = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+        assert!(matches!(
+            jit.request(elevation(DataSensitivity::Internal)).await,
+            Err(JitError::Duplicate(id)) if id == first.id
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_undecided_request_times_out() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+        let jit = jit.with_config(JitConfig {
+            approval_timeout: Duration::zero(),
+            ..JitConfig::default()
+        });
+
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert!(matches!(
+            jit.approve(&request.id, "bob", None).await,
+            Err(JitError::NotPending(JitStatus::TimedOut))
+        ));
+
+        assert!(jit.expire_due().await.is_empty());
+        assert_eq!(jit.get(&request.id).unwrap().status, JitStatus::TimedOut);
+        assert_eq!(jit.stats().lapsed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_grant_returns_sessions() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+        let request = jit.approve(&request.id, "bob", None).await.unwrap();
+
+        let grant = jit.active_grant("alice", "db-prod", AccessAction::Admin).unwrap();
+        jit.bind_session(&grant, "sess-1").await;
+        jit.bind_session(&grant, "sess-1").await;
+        assert!(jit.expire_due().await.is_empty());
+
+        jit.requests.get_mut(&request.id).unwrap().grant.as_mut().unwrap().expires_at =
+            Utc::now() - Duration::seconds(1);
+        assert_eq!(jit.expire_due().await, vec!["sess-1".to_string()]);
+        assert_eq!(jit.get(&request.id).unwrap().status, JitStatus::Expired);
+        assert!(jit.active_grant("alice", "db-prod", AccessAction::Admin).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_ends_grant() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+        jit.approve(&request.id, "bob", None).await.unwrap();
+
+        let grant = jit.revoke(&request.id, "soc", "suspicious queries").await.unwrap();
+        assert_eq!(grant.request_id, request.id);
+        assert!(jit.active_grant("alice", "db-prod", AccessAction::Admin).is_none());
+        assert!(matches!(
+            jit.revoke(&request.id, "soc", "again").await,
+            Err(JitError::NotActive(JitStatus::Revoked))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_signed_link_decides_request() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+
+        let approve = jit.sign_token(&request, "bob", true);
+        let request = jit.decide_with_token(&approve).await.unwrap();
+        assert_eq!(request.status, JitStatus::Active);
+        assert_eq!(
+            request.stages[0].decision.as_ref().unwrap().comment.as_deref(),
+            Some("via approval link")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_foreign_links_rejected() {
+        let (jit, _) = manager(StaticDirectory::new().with_manager("alice", "bob"));
+        let request = jit.request(elevation(DataSensitivity::Internal)).await.unwrap();
+
+        // Deny link with its claims swapped for an approval
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let deny = jit.sign_token(&request, "bob", false);
+        let (_, signature) = deny.split_once('.').unwrap();
+        let claims = format!("{}|bob|a|{}", request.id, request.decide_by.timestamp());
+        let forged = format!("{}.{}", engine.encode(claims), signature);
+        assert!(matches!(jit.decide_with_token(&forged).await, Err(JitError::InvalidToken)));
+        assert!(matches!(jit.decide_with_token("garbage").await, Err(JitError::InvalidToken)));
+
+        // Links signed by another node's key
+        let (other, _) = manager(StaticDirectory::new());
+        let foreign = other.sign_token(&request, "bob", true);
+        assert!(matches!(jit.decide_with_token(&foreign).await, Err(JitError::InvalidToken)));
+
+        let request = jit.decide_with_token(&deny).await.unwrap();
+        assert_eq!(request.status, JitStatus::Denied);
+    }
+
+    #[test]
+    fn test_expired_link_rejected() {
+        let (jit, _) = manager(StaticDirectory::new());
+        let mut request = JitRequest {
+            id: "req-1".to_string(),
+            user_id: "alice".to_string(),
+            resource_id: "db-prod".to_string(),
+            resource_name: "Production database".to_string(),
+            action: AccessAction::Read,
+            justification: "INC-42 failover repair".to_string(),
+            ticket: None,
+            duration_secs: 3600,
+            status: JitStatus::Pending,
+            stages: Vec::new(),
+            current_stage: 0,
+            created_at: Utc::now(),
+            decide_by: Utc::now() + Duration::hours(1),
+            grant: None,
+            history: Vec::new(),
+        };
+        let token = jit.sign_token(&request, "bob", true);
+        assert_eq!(
+            jit.verify_token(&token).unwrap(),
+            ("req-1".to_string(), "bob".to_string(), true)
+        );
+
+        request.decide_by = Utc::now() - Duration::seconds(1);
+        let token = jit.sign_token(&request, "bob", true);
+        assert!(matches!(jit.verify_token(&token), Err(JitError::InvalidToken)));
+    }
+
+    #[test]
+    fn test_approver_role_parse() {
+        assert_eq!(ApproverRole::parse(" Manager "), ApproverRole::Manager);
+        assert_eq!(ApproverRole::parse("owner"), ApproverRole::ResourceOwner);
+        assert_eq!(ApproverRole::parse("security"), ApproverRole::Soc);
+        assert_eq!(ApproverRole::parse("group:dbas"), ApproverRole::Group("dbas".to_string()));
+        assert_eq!(ApproverRole::parse("bob"), ApproverRole::User("bob".to_string()));
+    }
+}
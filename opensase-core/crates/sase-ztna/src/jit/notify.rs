@@ -0,0 +1,304 @@
+//! JIT Notifications
+//!
+//! Approval requests and outcomes go out through `ApprovalNotifier`
+//! implementations. Slack messages are posted to an incoming webhook;
+//! email is handed to an internal SMTP relay, one message per recipient so
+//! each approver gets their own signed links.
+
+use super::{ApproverDirectory, JitRequest};
+use std::collections::HashMap;
+use std::sync::Arc;
+use sase_common::smtp::{SmtpError, SmtpRelay};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Sent to the approvers of the current stage
+    ApprovalRequested,
+    Granted,
+    Denied,
+    /// Requester withdrew; sent to the approvers who were asked
+    Cancelled,
+    TimedOut,
+    Expired,
+    Revoked,
+}
+
+/// Signed one-click decision links for one approver
+#[derive(Debug, Clone)]
+pub struct ActionLinks {
+    pub approve: String,
+    pub deny: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct JitNotification {
+    pub kind: NotificationKind,
+    pub request: JitRequest,
+    /// User ids to tell
+    pub recipients: Vec<String>,
+    /// Decision links by approver, when an approval endpoint is configured
+    pub links: HashMap<String, ActionLinks>,
+}
+
+impl JitNotification {
+    pub fn subject(&self) -> String {
+        let request = &self.request;
+        match self.kind {
+            NotificationKind::ApprovalRequested => format!(
+                "Approval needed: {} requests {:?} on {}",
+                request.user_id, request.action, request.resource_name
+            ),
+            NotificationKind::Granted => format!("Access granted: {:?} on {}", request.action, request.resource_name),
+            NotificationKind::Denied => format!("Access denied: {:?} on {}", request.action, request.resource_name),
+            NotificationKind::Cancelled => format!("Request withdrawn: {:?} on {}", request.action, request.resource_name),
+            NotificationKind::TimedOut => format!("Request timed out: {:?} on {}", request.action, request.resource_name),
+            NotificationKind::Expired => format!("Access expired: {:?} on {}", request.action, request.resource_name),
+            NotificationKind::Revoked => format!("Access revoked: {:?} on {}", request.action, request.resource_name),
+        }
+    }
+
+    /// Plain-text body for one recipient
+    pub fn body(&self, recipient: &str) -> String {
+        let request = &self.request;
+        let mut body = format!(
+            "Requester: {}\nResource: {} ({})\nAction: {:?}\nDuration: {} minutes\nJustification: {}\n",
+            request.user_id,
+            request.resource_name,
+            request.resource_id,
+            request.action,
+            request.duration_secs / 60,
+            request.justification,
+        );
+        if let Some(ticket) = &request.ticket {
+            body.push_str(&format!("Ticket: {}\n", ticket));
+        }
+        if let Some(stage) = request.stages.get(request.current_stage) {
+            if self.kind == NotificationKind::ApprovalRequested {
+                body.push_str(&format!(
+                    "Approval stage: {} of {} ({})\nDecide by: {}\n",
+                    request.current_stage + 1,
+                    request.stages.len(),
+                    stage.role,
+                    request.decide_by.to_rfc3339(),
+                ));
+            }
+        }
+        if let Some(grant) = &request.grant {
+            body.push_str(&format!("Valid until: {}\n", grant.expires_at.to_rfc3339()));
+        }
+        if let Some(entry) = request.history.last() {
+            if let Some(comment) = &entry.comment {
+                if self.kind != NotificationKind::ApprovalRequested {
+                    body.push_str(&format!("Note from {}: {}\n", entry.actor, comment));
+                }
+            }
+        }
+        if let Some(links) = self.links.get(recipient) {
+            body.push_str(&format!("\nApprove: {}\nDeny: {}\n", links.approve, links.deny));
+        }
+        body.push_str(&format!("\nRequest ID: {}\n", request.id));
+        body
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ApprovalNotifier: Send + Sync {
+    fn name(&self) -> &str;
+    async fn notify(&self, notification: &JitNotification) -> Result<(), NotifyError>;
+}
+
+// =============================================================================
+// Slack
+// =============================================================================
+
+/// Posts to a Slack incoming webhook
+pub struct SlackNotifier {
+    webhook_url: String,
+    client: reqwest::Client,
+    /// Slack member ids by user id, for mentions
+    members: HashMap<String, String>,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            webhook_url: webhook_url.to_string(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            members: HashMap::new(),
+        }
+    }
+
+    pub fn with_member(mut self, user_id: &str, slack_member_id: &str) -> Self {
+        self.members.insert(user_id.to_string(), slack_member_id.to_string());
+        self
+    }
+
+    fn mention(&self, user_id: &str) -> String {
+        match self.members.get(user_id) {
+            Some(member) => format!("<@{}>", member),
+            None => user_id.to_string(),
+        }
+    }
+
+    fn payload(&self, notification: &JitNotification) -> serde_json::Value {
+        let request = &notification.request;
+        let recipients: Vec<String> = notification.recipients.iter().map(|r| self.mention(r)).collect();
+
+        let mut fields = vec![
+            serde_json::json!({ "type": "mrkdwn", "text": format!("*Requester:*\n{}", self.mention(&request.user_id)) }),
+            serde_json::json!({ "type": "mrkdwn", "text": format!("*Resource:*\n{}", request.resource_name) }),
+            serde_json::json!({ "type": "mrkdwn", "text": format!("*Action:*\n{:?}", request.action) }),
+            serde_json::json!({ "type": "mrkdwn", "text": format!("*Duration:*\n{} min", request.duration_secs / 60) }),
+        ];
+        if let Some(ticket) = &request.ticket {
+            fields.push(serde_json::json!({ "type": "mrkdwn", "text": format!("*Ticket:*\n{}", ticket) }));
+        }
+
+        let mut blocks = vec![
+            serde_json::json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": format!("*{}*\n{}", notification.subject(), recipients.join(" ")) },
+            }),
+            serde_json::json!({ "type": "section", "fields": fields }),
+            serde_json::json!({
+                "type": "context",
+                "elements": [{ "type": "mrkdwn", "text": format!("Justification: {}", request.justification) }],
+            }),
+        ];
+
+        // One pair of buttons per approver; the links are signed for them
+        for recipient in &notification.recipients {
+            let Some(links) = notification.links.get(recipient) else { continue };
+            blocks.push(serde_json::json!({
+                "type": "actions",
+                "elements": [
+                    {
+                        "type": "button",
+                        "style": "primary",
+                        "text": { "type": "plain_text", "text": format!("Approve ({})", recipient) },
+                        "url": links.approve,
+                    },
+                    {
+                        "type": "button",
+                        "style": "danger",
+                        "text": { "type": "plain_text", "text": format!("Deny ({})", recipient) },
+                        "url": links.deny,
+                    },
+                ],
+            }));
+        }
+
+        serde_json::json!({
+            "text": notification.subject(),
+            "blocks": blocks,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalNotifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn notify(&self, notification: &JitNotification) -> Result<(), NotifyError> {
+        let response = self.client
+            .post(&self.webhook_url)
+            .json(&self.payload(notification))
+            .send()
+            .await
+            .map_err(|e| NotifyError::Transport(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(NotifyError::Rejected(format!("Slack webhook returned {}", response.status())));
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Email
+// =============================================================================
+
+/// Sends through an SMTP relay that accepts mail from the gateway without
+/// authentication (an internal MTA or the email security gateway)
+pub struct EmailNotifier {
+    relay: SmtpRelay,
+    directory: Arc<dyn ApproverDirectory>,
+    timeout: Duration,
+}
+
+impl EmailNotifier {
+    pub fn new(relay: &str, from: &str, directory: Arc<dyn ApproverDirectory>) -> Self {
+        Self {
+            relay: SmtpRelay::new(relay, from).with_helo("ztna.localdomain"),
+            directory,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_helo(mut self, helo: &str) -> Self {
+        self.relay.helo = helo.to_string();
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalNotifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn notify(&self, notification: &JitNotification) -> Result<(), NotifyError> {
+        let subject = notification.subject();
+        let mut failures = Vec::new();
+
+        for recipient in &notification.recipients {
+            let Some(address) = self.directory.email_of(recipient) else {
+                failures.push(format!("{}: no email address", recipient));
+                continue;
+            };
+            let body = notification.body(recipient);
+            match tokio::time::timeout(self.timeout, self.relay.send(&address, &subject, &body)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => failures.push(format!("{}: {}", recipient, NotifyError::from(e))),
+                Err(_) => failures.push(format!("{}: timed out", recipient)),
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifyError::Rejected(failures.join("; ")))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NotifyError {
+    Transport(String),
+    Rejected(String),
+}
+
+impl std::fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "Notification transport error: {}", e),
+            Self::Rejected(e) => write!(f, "Notification rejected: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for NotifyError {}
+
+impl From<SmtpError> for NotifyError {
+    fn from(e: SmtpError) -> Self {
+        match e {
+            SmtpError::Transport(e) => Self::Transport(e),
+            SmtpError::Rejected(e) => Self::Rejected(e),
+        }
+    }
+}
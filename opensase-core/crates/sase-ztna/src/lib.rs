@@ -62,6 +62,10 @@ pub mod clientless;
 pub mod recording;
 pub mod microseg_enhanced;
 pub mod stepup;
+pub mod investigation;
+pub mod jit;
+pub mod pki;
+pub mod watchlist;
 
 // =============================================================================
 // Core Types
@@ -138,6 +142,15 @@ pub struct DevicePosture {
     pub screen_lock_enabled: bool,
     pub jailbroken: bool,
     pub last_checked: DateTime<Utc>,
+    /// Security products reported running (EDR, antivirus)
+    #[serde(default)]
+    pub antivirus_products: Vec<String>,
+    /// Days since the last OS patch was installed
+    #[serde(default)]
+    pub os_patch_age_days: Option<u32>,
+    /// Further agent-reported facts, for `attr.<key>` in posture policies
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -223,9 +236,15 @@ pub struct GeoLocation {
     pub city: Option<String>,
     pub latitude: f64,
     pub longitude: f64,
+    /// Origin AS of the client address
+    #[serde(default)]
+    pub asn: Option<u32>,
+    /// Geo-IP accuracy radius in kilometres
+    #[serde(default)]
+    pub accuracy_km: Option<f64>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum NetworkType {
     Corporate,
     VPN,
@@ -243,7 +262,7 @@ pub struct RiskSignal {
     pub detected_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum RiskSignalType {
     ImpossibleTravel,
     NewDevice,
@@ -289,12 +308,18 @@ pub enum Decision {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AccessCondition {
     RequireMfa,
+    /// MFA with an origin-bound factor (WebAuthn passkey or security key)
+    RequirePhishingResistantMfa,
     RequireDeviceCompliance,
     TimeRestriction { allowed_hours: (u8, u8) },
     LocationRestriction { allowed_countries: Vec<String> },
     ReadOnly,
     SessionTimeout { minutes: u32 },
     RequireApproval { approver: String },
+    /// Serve through remote browser isolation
+    BrowserIsolation { mode: sase_rbi::IsolationMode },
+    /// Posture rules the device fails, with how to fix them
+    RemediatePosture { hints: Vec<posture::RemediationHint> },
 }
 
 /// Session
@@ -328,18 +353,32 @@ pub enum SessionStatus {
 pub struct ZeroTrustGateway {
     /// Identity engine
     identity_engine: identity::IdentityEngine,
-    /// Policy engine
-    policy_engine: policy::PolicyEngine,
+    /// Policy engine, shared with the policy store for hot reloads
+    policy_engine: Arc<policy::PolicyEngine>,
     /// Risk engine
     risk_engine: risk::RiskEngine,
-    /// Session manager
-    session_manager: session::SessionManager,
+    /// Session manager, shared with the continuous evaluator
+    session_manager: Arc<session::SessionManager>,
     /// Continuous evaluator
     continuous_evaluator: continuous::ContinuousEvaluator,
     /// Micro-segmentation
     microseg: microseg::MicroSegmentationEngine,
     /// Audit logger
-    audit: audit::AuditLogger,
+    audit: Arc<audit::AuditLogger>,
+    /// Session mappings for insider investigations
+    investigations: Option<Arc<investigation::ActivityReporter>>,
+    /// Just-in-time grants for approval-gated resources
+    jit: Option<Arc<jit::JitManager>>,
+    /// Device CA, for revoking certificates on compromise signals
+    device_ca: Option<Arc<pki::DeviceCa>>,
+    /// Federated login through tenant IdPs
+    sso: Option<Arc<sso::SsoManager>>,
+    /// Inline step-up for sessions whose risk rose
+    step_up: Option<Arc<stepup::StepUpManager>>,
+    /// High-risk users and hosts flagged by the SOC
+    watchlist: Option<Arc<watchlist::Watchlist>>,
+    /// Tenant posture policies scoring device trust
+    posture_policies: Option<Arc<posture::PosturePolicyEngine>>,
     /// Config
     config: ZtnaConfig,
 }
@@ -354,6 +393,8 @@ pub struct ZtnaConfig {
     pub require_mfa_for_sensitive: bool,
     /// Continuous evaluation interval (seconds)
     pub evaluation_interval_secs: u64,
+    /// Risk bands at which continuous evaluation acts on a session
+    pub risk_thresholds: continuous::RiskThresholds,
 }
 
 impl Default for ZtnaConfig {
@@ -363,26 +404,147 @@ impl Default for ZtnaConfig {
             high_risk_threshold: 70.0,
             require_mfa_for_sensitive: true,
             evaluation_interval_secs: 30,
+            risk_thresholds: continuous::RiskThresholds::default(),
         }
     }
 }
 
 impl ZeroTrustGateway {
     pub fn new(config: ZtnaConfig) -> Self {
+        let session_manager = Arc::new(session::SessionManager::new(config.session_timeout_mins));
+        let audit = Arc::new(audit::AuditLogger::new());
+        let continuous_evaluator = continuous::ContinuousEvaluator::new(
+            config.evaluation_interval_secs,
+            session_manager.clone(),
+            audit.clone(),
+        ).with_thresholds(config.risk_thresholds);
+        
         Self {
             identity_engine: identity::IdentityEngine::new(),
-            policy_engine: policy::PolicyEngine::new(),
+            policy_engine: Arc::new(policy::PolicyEngine::new()),
             risk_engine: risk::RiskEngine::new(),
-            session_manager: session::SessionManager::new(config.session_timeout_mins),
-            continuous_evaluator: continuous::ContinuousEvaluator::new(config.evaluation_interval_secs),
+            session_manager,
+            continuous_evaluator,
             microseg: microseg::MicroSegmentationEngine::new(),
-            audit: audit::AuditLogger::new(),
+            audit,
+            investigations: None,
+            jit: None,
+            device_ca: None,
+            sso: None,
+            step_up: None,
+            watchlist: None,
+            posture_policies: None,
             config,
         }
     }
     
+    /// Record session address mappings for activity reports. The reporter
+    /// should share this gateway's audit logger.
+    pub fn with_activity_reporter(mut self, reporter: Arc<investigation::ActivityReporter>) -> Self {
+        self.investigations = Some(reporter);
+        self
+    }
+    
+    /// Let approval-gated access through on live JIT grants. The manager
+    /// should share this gateway's audit logger.
+    pub fn with_jit(mut self, jit: Arc<jit::JitManager>) -> Self {
+        self.jit = Some(jit);
+        self
+    }
+    
+    pub fn jit(&self) -> Option<Arc<jit::JitManager>> {
+        self.jit.clone()
+    }
+    
+    /// Revoke device certificates when requests carry compromise signals,
+    /// and refuse devices presenting revoked ones. The CA should share
+    /// this gateway's audit logger.
+    pub fn with_device_ca(mut self, device_ca: Arc<pki::DeviceCa>) -> Self {
+        self.device_ca = Some(device_ca);
+        self
+    }
+    
+    pub fn device_ca(&self) -> Option<Arc<pki::DeviceCa>> {
+        self.device_ca.clone()
+    }
+    
+    /// Accept logins federated through tenant IdPs. The manager should
+    /// share this gateway's audit logger.
+    pub fn with_sso(mut self, sso: Arc<sso::SsoManager>) -> Self {
+        self.sso = Some(sso);
+        self
+    }
+    
+    pub fn sso(&self) -> Option<Arc<sso::SsoManager>> {
+        self.sso.clone()
+    }
+    
+    /// Challenge sessions whose risk rose at their next request instead
+    /// of suspending them
+    pub fn with_step_up(mut self, step_up: Arc<stepup::StepUpManager>) -> Self {
+        self.continuous_evaluator = self.continuous_evaluator.with_step_up(step_up.clone());
+        self.step_up = Some(step_up);
+        self
+    }
+    
+    pub fn step_up(&self) -> Option<Arc<stepup::StepUpManager>> {
+        self.step_up.clone()
+    }
+    
+    /// Tighten access for watchlisted users and devices: extra request
+    /// risk, an MFA condition, and re-scoring of their live sessions
+    pub fn with_watchlist(mut self, watchlist: Arc<watchlist::Watchlist>) -> Self {
+        self.continuous_evaluator = self.continuous_evaluator.with_signal_source(watchlist.clone());
+        self.watchlist = Some(watchlist);
+        self
+    }
+    
+    pub fn watchlist(&self) -> Option<Arc<watchlist::Watchlist>> {
+        self.watchlist.clone()
+    }
+    
+    /// Score devices against their tenant's posture policy. Device trust
+    /// is capped at the posture trust level, and failed rules are returned
+    /// to the client as remediation hints.
+    pub fn with_posture_policies(mut self, policies: Arc<posture::PosturePolicyEngine>) -> Self {
+        self.posture_policies = Some(policies);
+        self
+    }
+    
+    pub fn posture_policies(&self) -> Option<Arc<posture::PosturePolicyEngine>> {
+        self.posture_policies.clone()
+    }
+    
+    /// Re-score monitored sessions with posture and signals from `source`
+    pub fn with_signal_source(mut self, source: Arc<dyn continuous::SessionSignalSource>) -> Self {
+        self.continuous_evaluator = self.continuous_evaluator.with_signal_source(source);
+        self
+    }
+    
+    /// Policy engine, e.g. for a [`policy::store::GitPolicyStore`] to
+    /// install policies into
+    pub fn policy_engine(&self) -> Arc<policy::PolicyEngine> {
+        self.policy_engine.clone()
+    }
+    
+    /// Audit trail shared with other components
+    pub fn audit_logger(&self) -> Arc<audit::AuditLogger> {
+        self.audit.clone()
+    }
+
+    /// Geo-velocity engine behind impossible-travel signals, e.g. to
+    /// register VPN egress ranges
+    pub fn geo_velocity(&self) -> &risk::velocity::GeoVelocityEngine {
+        self.risk_engine.velocity()
+    }
+
+    /// Current state of a session
+    pub fn session(&self, session_id: &str) -> Option<Session> {
+        self.session_manager.get(session_id)
+    }
+    
     /// Process access request
-    pub async fn request_access(&self, request: AccessRequest) -> AccessDecision {
+    pub async fn request_access(&self, mut request: AccessRequest) -> AccessDecision {
         let start = std::time::Instant::now();
         
         // 1. Verify identity
@@ -391,14 +553,62 @@ impl ZeroTrustGateway {
             return self.deny_access(&request, "Identity verification failed").await;
         }
         
-        // 2. Assess device trust
-        let device_trust = self.identity_engine.assess_device(&request.device).await;
+        // 1a. Sessions flagged for step-up answer a challenge first
+        if let (Some(step_up), Some(session_id)) = (&self.step_up, &request.context.session_id) {
+            let session = self.session_manager.get(session_id)
+                .filter(|s| s.identity.user_id == request.identity.user_id);
+            if let Some(session) = session {
+                match step_up.enforce(&session).await {
+                    stepup::StepUpGate::Proceed => {}
+                    stepup::StepUpGate::Challenge(challenge) => {
+                        return self.step_up_access(&request, &session, &challenge).await;
+                    }
+                    stepup::StepUpGate::Terminate(reason) => {
+                        self.terminate_session(&session.id).await;
+                        return self.deny_access(&request, &reason).await;
+                    }
+                }
+            }
+        }
+        
+        // 1b. Compromise signals revoke the device's certificates
+        if let Some(device_ca) = &self.device_ca {
+            for signal in &request.context.signals {
+                if !device_ca.handle_risk_signal(&request.device.id, signal).await.is_empty() {
+                    return self.deny_access(&request, "Device certificates revoked after compromise signal").await;
+                }
+            }
+            let revoked = request.device.certificates.iter()
+                .any(|c| device_ca.get(&c.id).is_some_and(|issued| issued.is_revoked()));
+            if revoked {
+                return self.deny_access(&request, "Device certificate revoked").await;
+            }
+        }
+        
+        // 2. Assess device trust, capped by the tenant's posture policy
+        let mut device_trust = self.identity_engine.assess_device(&request.device).await;
+        let posture = self.posture_policies.as_ref().and_then(|p| p.evaluate(&request));
+        if let Some(posture) = &posture {
+            if posture.blocked() {
+                return self.remediate_posture(&request, posture).await;
+            }
+            device_trust = device_trust.min(posture.trust_level);
+            // Policies and the session see the scored trust, not the reported one
+            request.device.trust_level = request.device.trust_level.min(posture.trust_level);
+            request.device.compliant &= posture.compliant;
+        }
         if device_trust < TrustLevel::Low {
             return self.deny_access(&request, "Device trust insufficient").await;
         }
         
-        // 3. Evaluate risk
-        let risk_score = self.risk_engine.evaluate(&request).await;
+        // 3. Evaluate risk; watchlisted users and devices get less headroom
+        let mut risk_score = self.risk_engine.evaluate(&request).await;
+        let watched = self.watchlist.as_ref()
+            .and_then(|w| w.check(&request.identity.user_id, &request.device).map(|m| (w.clone(), m)));
+        if let Some((watchlist, watch)) = &watched {
+            risk_score += watchlist.risk_for(watch);
+            tracing::info!("Access request {}: {}", request.id, watch.reason);
+        }
         if risk_score > self.config.high_risk_threshold {
             return self.challenge_access(&request, risk_score).await;
         }
@@ -409,31 +619,85 @@ impl ZeroTrustGateway {
             return self.deny_access(&request, &policy_decision.reasons.join(", ")).await;
         }
         
+        // 4b. Approval-gated resources need a live JIT grant
+        let approver = policy_decision.conditions.iter().find_map(|c| match c {
+            AccessCondition::RequireApproval { approver } => Some(approver.clone()),
+            _ => None,
+        });
+        let jit_grant = match approver {
+            Some(approver) => {
+                let grant = self.jit.as_ref().and_then(|jit| {
+                    jit.active_grant(&request.identity.user_id, &request.resource.id, request.action)
+                });
+                match grant {
+                    Some(grant) => Some(grant),
+                    None => return self.review_access(&request, &approver).await,
+                }
+            }
+            None => None,
+        };
+        
         // 5. Check micro-segmentation
         if !self.microseg.is_allowed(&request).await {
             return self.deny_access(&request, "Network segmentation policy denied").await;
         }
         
-        // 6. Create/update session
+        // 6. Route web resources through SWG (direct, isolate or block)
+        let mut conditions = policy_decision.conditions.clone();
+        if jit_grant.is_some() {
+            conditions.retain(|c| !matches!(c, AccessCondition::RequireApproval { .. }));
+        }
+        if let Some(condition) = watched.as_ref().and_then(|(watchlist, watch)| watchlist.condition_for(watch)) {
+            conditions.retain(|c| !matches!(c, AccessCondition::RequireMfa));
+            if !conditions.iter().any(|c| matches!(c, AccessCondition::RequirePhishingResistantMfa)) {
+                conditions.push(condition);
+            }
+        }
+        if let Some(posture) = posture.filter(|p| !p.compliant) {
+            conditions.push(AccessCondition::RemediatePosture { hints: posture.failures });
+        }
+        if let Some(url) = request.resource.tags.get("url") {
+            match sase_rbi::swg::decide(url, &swg_context(&request, device_trust)) {
+                sase_rbi::swg::AccessRoute::Block { reason } => {
+                    return self.deny_access(&request, &format!("Web gateway: {}", reason)).await;
+                }
+                sase_rbi::swg::AccessRoute::Isolate { mode, .. } => {
+                    conditions.push(AccessCondition::BrowserIsolation { mode });
+                }
+                sase_rbi::swg::AccessRoute::Direct => {}
+            }
+        }
+        
+        // 7. Create/update session
         let session = self.session_manager.create_or_update(
             &request.identity,
             &request.device,
             &request.resource,
         ).await;
         
-        // 7. Log access
+        // 8. Log access
         self.audit.log_access(&request, &policy_decision, start.elapsed()).await;
+        if let Some(investigations) = &self.investigations {
+            investigations.map_session(&session, request.context.client_ip);
+        }
         
-        // 8. Start continuous monitoring
+        // 9. Start continuous monitoring
         self.continuous_evaluator.register_session(&session).await;
         
+        // 10. Access under a JIT grant ends with the grant
+        let mut expires_at = session.expires_at;
+        if let (Some(jit), Some(grant)) = (&self.jit, &jit_grant) {
+            jit.bind_session(grant, &session.id).await;
+            expires_at = expires_at.min(grant.expires_at);
+        }
+        
         AccessDecision {
             request_id: request.id,
             decision: Decision::Allow,
             reasons: vec!["All checks passed".to_string()],
-            conditions: policy_decision.conditions,
+            conditions,
             session_id: Some(session.id),
-            expires_at: Some(session.expires_at),
+            expires_at: Some(expires_at),
             evaluated_at: Utc::now(),
         }
     }
@@ -452,6 +716,28 @@ impl ZeroTrustGateway {
         }
     }
     
+    async fn remediate_posture(
+        &self,
+        request: &AccessRequest,
+        posture: &posture::PostureEvaluation,
+    ) -> AccessDecision {
+        let reason = format!(
+            "Device posture insufficient: score {:.0} under tenant {} policy v{}",
+            posture.score, posture.tenant_id, posture.policy_version
+        );
+        self.audit.log_denial(request, &reason).await;
+        
+        AccessDecision {
+            request_id: request.id.clone(),
+            decision: Decision::Deny,
+            reasons: vec![reason],
+            conditions: vec![AccessCondition::RemediatePosture { hints: posture.failures.clone() }],
+            session_id: None,
+            expires_at: None,
+            evaluated_at: Utc::now(),
+        }
+    }
+    
     async fn challenge_access(&self, request: &AccessRequest, risk_score: f64) -> AccessDecision {
         self.audit.log_challenge(request, risk_score).await;
         
@@ -466,10 +752,181 @@ impl ZeroTrustGateway {
         }
     }
     
+    async fn step_up_access(
+        &self,
+        request: &AccessRequest,
+        session: &Session,
+        challenge: &stepup::StepUpChallenge,
+    ) -> AccessDecision {
+        self.audit.log_challenge(request, session.risk_score).await;
+        
+        AccessDecision {
+            request_id: request.id.clone(),
+            decision: Decision::StepUp,
+            reasons: vec![format!("Step-up required ({:?}): challenge {}", challenge.reason, challenge.id)],
+            conditions: vec![challenge.challenge_type.condition()],
+            session_id: Some(session.id.clone()),
+            expires_at: Some(challenge.expires_at),
+            evaluated_at: Utc::now(),
+        }
+    }
+    
+    /// Answer a session's step-up challenge. Passing lowers the session's
+    /// monitored risk; running out of attempts terminates the session.
+    pub async fn verify_step_up(
+        &self,
+        challenge_id: &str,
+        response: &str,
+    ) -> Result<stepup::StepUpResult, stepup::StepUpError> {
+        let step_up = self.step_up.as_ref().ok_or(stepup::StepUpError::ChallengeNotFound)?;
+        let result = step_up.verify(challenge_id, response).await;
+        
+        let Some(challenge) = step_up.challenge(challenge_id) else {
+            return result;
+        };
+        let method = format!("step-up:{:?}", challenge.challenge_type);
+        self.audit.log_authentication(&challenge.user_id, result.is_ok(), &method).await;
+        match &result {
+            Ok(passed) => self.continuous_evaluator.relieve(&passed.session_id, passed.trust_bonus),
+            Err(_) => {
+                if matches!(step_up.state(&challenge.session_id), stepup::StepUpState::Failed { .. }) {
+                    self.terminate_session(&challenge.session_id).await;
+                }
+            }
+        }
+        result
+    }
+    
+    async fn review_access(&self, request: &AccessRequest, approver: &str) -> AccessDecision {
+        let reason = format!("Approval required from {}", approver);
+        self.audit.log_denial(request, &reason).await;
+        
+        AccessDecision {
+            request_id: request.id.clone(),
+            decision: Decision::Review,
+            reasons: vec![reason],
+            conditions: vec![AccessCondition::RequireApproval { approver: approver.to_string() }],
+            session_id: None,
+            expires_at: None,
+            evaluated_at: Utc::now(),
+        }
+    }
+    
+    /// Close out lapsed JIT requests and grants and terminate the sessions
+    /// opened under them. Call periodically; returns sessions terminated.
+    pub async fn expire_jit_grants(&self) -> usize {
+        let Some(jit) = &self.jit else { return 0 };
+        let sessions = jit.expire_due().await;
+        for session_id in &sessions {
+            self.terminate_session(session_id).await;
+        }
+        sessions.len()
+    }
+    
+    /// End a JIT grant early and terminate its sessions
+    pub async fn revoke_jit_grant(
+        &self,
+        request_id: &str,
+        actor_id: &str,
+        reason: &str,
+    ) -> Result<jit::JitGrant, jit::JitError> {
+        let jit = self.jit.as_ref().ok_or_else(|| jit::JitError::NotFound(request_id.to_string()))?;
+        let grant = jit.revoke(request_id, actor_id, reason).await?;
+        for session_id in &grant.session_ids {
+            self.terminate_session(session_id).await;
+        }
+        Ok(grant)
+    }
+    
+    /// Open a session for a completed federated login. The user's earlier
+    /// sessions on the device are terminated so a login always gets a
+    /// fresh session id.
+    pub async fn establish_sso_session(&self, login: &sso::SsoLogin, device: &Device) -> Session {
+        let previous: Vec<String> = self.session_manager.get_user_sessions(&login.identity.user_id)
+            .into_iter()
+            .filter(|s| s.device.id == device.id)
+            .map(|s| s.id)
+            .collect();
+        for session_id in &previous {
+            self.terminate_session(session_id).await;
+        }
+        
+        let session = self.session_manager.establish(&login.identity, device, login.not_after).await;
+        self.continuous_evaluator.register_session(&session).await;
+        self.audit.log_session_created(&session, &format!("sso:{}", login.provider_id)).await;
+        session
+    }
+    
     /// Terminate session
     pub async fn terminate_session(&self, session_id: &str) {
         self.session_manager.terminate(session_id).await;
         self.continuous_evaluator.unregister_session(session_id).await;
         self.audit.log_session_termination(session_id).await;
+        self.release_session(session_id);
+    }
+    
+    /// Re-score every monitored session once, acting on those whose risk
+    /// crossed a threshold. Returns the sessions acted on.
+    pub async fn evaluate_sessions(&self) -> Vec<continuous::EvaluationResult> {
+        let results = self.continuous_evaluator.evaluate_all(&self.risk_engine).await;
+        for result in &results {
+            if result.action == continuous::EvaluationAction::Revoke {
+                self.release_session(&result.session_id);
+            }
+        }
+        results
+    }
+    
+    /// Force re-evaluation of one session
+    pub async fn reevaluate_session(&self, session_id: &str) -> Option<continuous::EvaluationResult> {
+        let result = self.continuous_evaluator.reevaluate(session_id, &self.risk_engine).await?;
+        if result.action == continuous::EvaluationAction::Revoke {
+            self.release_session(session_id);
+        }
+        Some(result)
+    }
+    
+    /// Run [`Self::evaluate_sessions`] every evaluation interval
+    pub fn spawn_continuous_evaluation(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.continuous_evaluator.interval());
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let results = self.evaluate_sessions().await;
+                if !results.is_empty() {
+                    tracing::debug!("Continuous evaluation acted on {} sessions", results.len());
+                }
+            }
+        })
+    }
+    
+    /// Drop per-session state held outside the session manager
+    fn release_session(&self, session_id: &str) {
+        if let Some(step_up) = &self.step_up {
+            step_up.end_session(session_id);
+        }
+        if let Some(investigations) = &self.investigations {
+            investigations.end_session(session_id);
+        }
+    }
+}
+
+/// SWG user context for a ZTNA access request
+fn swg_context(request: &AccessRequest, device_trust: TrustLevel) -> sase_rbi::swg::UserContext {
+    use sase_rbi::swg::DeviceTrust;
+    
+    let trust = match device_trust {
+        TrustLevel::Untrusted => DeviceTrust::Untrusted,
+        TrustLevel::Low => DeviceTrust::Low,
+        TrustLevel::Medium => DeviceTrust::Medium,
+        TrustLevel::High => DeviceTrust::High,
+        TrustLevel::Full => DeviceTrust::Full,
+    };
+    sase_rbi::swg::UserContext {
+        source_ip: Some(request.context.client_ip),
+        ..sase_rbi::swg::UserContext::new(&request.identity.user_id)
+            .with_groups(request.identity.groups.clone())
+            .with_device(trust, request.device.managed)
     }
 }
@@ -2,9 +2,11 @@
 //!
 //! MFA engine supporting multiple authentication factors.
 
-use crate::Identity;
+use crate::{AccessCondition, Identity};
 use std::collections::HashMap;
 
+pub mod webauthn;
+
 /// MFA Engine
 pub struct MfaEngine {
     /// User MFA registrations
@@ -13,6 +15,8 @@ pub struct MfaEngine {
     pending_challenges: dashmap::DashMap<String, MfaChallenge>,
     /// TOTP validator
     totp_validator: TotpValidator,
+    /// WebAuthn relying party; absent until configured
+    webauthn: Option<webauthn::WebAuthnManager>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +40,13 @@ pub enum MfaFactorType {
     Biometric,
 }
 
+impl MfaFactorType {
+    /// Bound to the origin, so a proxy phishing site cannot relay it
+    pub fn is_phishing_resistant(&self) -> bool {
+        matches!(self, Self::WebAuthn)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MfaChallenge {
     pub id: String,
@@ -60,17 +71,107 @@ pub struct MfaVerifyResult {
     pub success: bool,
     pub factor_type: MfaFactorType,
     pub message: Option<String>,
+    /// Satisfied with a phishing-resistant factor
+    pub phishing_resistant: bool,
+    /// The authenticator verified the user (PIN or biometric)
+    pub user_verified: bool,
+}
+
+impl MfaVerifyResult {
+    fn failed(factor_type: MfaFactorType, message: &str) -> Self {
+        Self {
+            success: false,
+            factor_type,
+            message: Some(message.to_string()),
+            phishing_resistant: false,
+            user_verified: false,
+        }
+    }
+    
+    /// Whether this verification satisfies an MFA access condition
+    pub fn satisfies(&self, condition: &AccessCondition) -> bool {
+        match condition {
+            AccessCondition::RequireMfa => self.success,
+            AccessCondition::RequirePhishingResistantMfa => self.success && self.phishing_resistant,
+            _ => false,
+        }
+    }
 }
 
+/// Factor preference when a condition lets us choose: phishing-resistant
+/// first, out-of-band codes last
+const FACTOR_PREFERENCE: [MfaFactorType; 7] = [
+    MfaFactorType::WebAuthn,
+    MfaFactorType::Totp,
+    MfaFactorType::Push,
+    MfaFactorType::HardwareToken,
+    MfaFactorType::Biometric,
+    MfaFactorType::Sms,
+    MfaFactorType::Email,
+];
+
 impl MfaEngine {
     pub fn new() -> Self {
         Self {
             user_factors: dashmap::DashMap::new(),
             pending_challenges: dashmap::DashMap::new(),
             totp_validator: TotpValidator::new(),
+            webauthn: None,
         }
     }
     
+    /// Enable WebAuthn (passkeys and security keys)
+    pub fn with_webauthn(mut self, config: webauthn::WebAuthnConfig) -> Self {
+        self.webauthn = Some(webauthn::WebAuthnManager::new(config));
+        self
+    }
+    
+    /// Persist WebAuthn credentials, restoring factors for any already stored
+    pub fn with_webauthn_store(
+        mut self,
+        store: std::sync::Arc<dyn webauthn::CredentialStore>,
+    ) -> Result<Self, MfaError> {
+        let manager = self.webauthn.take().ok_or(MfaError::WebAuthnDisabled)?;
+        let manager = manager.with_store(store).map_err(MfaError::WebAuthn)?;
+        
+        for credential in manager.all_credentials() {
+            let name = if credential.backup_eligible { "Passkey" } else { "Security key" };
+            self.register_factor(&credential.user_id, webauthn_factor(&credential, name));
+        }
+        
+        self.webauthn = Some(manager);
+        Ok(self)
+    }
+    
+    pub fn webauthn(&self) -> Option<&webauthn::WebAuthnManager> {
+        self.webauthn.as_ref()
+    }
+    
+    /// Begin enrolling a passkey or security key
+    pub fn begin_webauthn_registration(
+        &self,
+        identity: &Identity,
+        tenant_id: &str,
+    ) -> Result<webauthn::RegistrationOptions, MfaError> {
+        let manager = self.webauthn.as_ref().ok_or(MfaError::WebAuthnDisabled)?;
+        Ok(manager.start_registration(&identity.user_id, &identity.email, &identity.name, tenant_id))
+    }
+    
+    /// Complete enrolment and register the credential as an MFA factor
+    pub fn finish_webauthn_registration(
+        &self,
+        response: &webauthn::RegistrationResponse,
+        name: &str,
+    ) -> Result<MfaFactor, MfaError> {
+        let manager = self.webauthn.as_ref().ok_or(MfaError::WebAuthnDisabled)?;
+        let credential = manager.finish_registration(response).map_err(MfaError::WebAuthn)?;
+        
+        let factor = webauthn_factor(&credential, name);
+        self.register_factor(&credential.user_id, factor.clone());
+        
+        Ok(factor)
+    }
+    
     /// Check if user has MFA enabled
     pub fn is_mfa_enabled(&self, user_id: &str) -> bool {
         self.user_factors.get(user_id)
@@ -85,6 +186,29 @@ impl MfaEngine {
             .unwrap_or_default()
     }
     
+    /// Challenge the user with the factor that best satisfies an access
+    /// condition. `RequirePhishingResistantMfa` only accepts WebAuthn.
+    pub async fn challenge_for_condition(
+        &self,
+        user_id: &str,
+        condition: &AccessCondition,
+    ) -> Result<MfaChallenge, MfaError> {
+        let registered: Vec<MfaFactorType> = self.user_factors.get(user_id)
+            .ok_or(MfaError::NoFactorsRegistered)?
+            .iter()
+            .map(|f| f.factor_type)
+            .collect();
+        
+        let phishing_resistant_only = matches!(condition, AccessCondition::RequirePhishingResistantMfa);
+        let factor_type = FACTOR_PREFERENCE.iter()
+            .copied()
+            .filter(|t| !phishing_resistant_only || t.is_phishing_resistant())
+            .find(|t| registered.contains(t))
+            .ok_or(MfaError::FactorNotRegistered)?;
+        
+        self.create_challenge(user_id, factor_type).await
+    }
+    
     /// Create MFA challenge
     pub async fn create_challenge(
         &self,
@@ -99,7 +223,7 @@ impl MfaEngine {
             return Err(MfaError::FactorNotRegistered);
         }
         
-        let challenge = MfaChallenge {
+        let mut challenge = MfaChallenge {
             id: uuid::Uuid::new_v4().to_string(),
             user_id: user_id.to_string(),
             factor_type,
@@ -114,6 +238,15 @@ impl MfaEngine {
             MfaFactorType::Push => self.send_push_notification(user_id, &challenge).await?,
             MfaFactorType::Sms => self.send_sms_code(user_id, &challenge).await?,
             MfaFactorType::Email => self.send_email_code(user_id, &challenge).await?,
+            MfaFactorType::WebAuthn => {
+                // The client runs navigator.credentials.get with these options
+                let manager = self.webauthn.as_ref().ok_or(MfaError::WebAuthnDisabled)?;
+                let options = manager.start_assertion(user_id).map_err(MfaError::WebAuthn)?;
+                let options_json = serde_json::to_string(&options)
+                    .map_err(|_| MfaError::ChallengeFailed)?;
+                challenge.metadata.insert("webauthn_challenge".to_string(), options.challenge);
+                challenge.metadata.insert("webauthn_options".to_string(), options_json);
+            }
             _ => {}
         }
         
@@ -130,27 +263,30 @@ impl MfaEngine {
     ) -> MfaVerifyResult {
         let mut challenge = match self.pending_challenges.get_mut(challenge_id) {
             Some(c) => c,
-            None => return MfaVerifyResult {
-                success: false,
-                factor_type: MfaFactorType::Totp,
-                message: Some("Challenge not found".to_string()),
-            },
+            None => return MfaVerifyResult::failed(MfaFactorType::Totp, "Challenge not found"),
         };
         
+        if challenge.state != ChallengeState::Pending {
+            return MfaVerifyResult::failed(challenge.factor_type, "Challenge already used");
+        }
+        
         // Check expiration
         if chrono::Utc::now() > challenge.expires_at {
             challenge.state = ChallengeState::Expired;
-            return MfaVerifyResult {
-                success: false,
-                factor_type: challenge.factor_type,
-                message: Some("Challenge expired".to_string()),
-            };
+            return MfaVerifyResult::failed(challenge.factor_type, "Challenge expired");
         }
         
         // Verify based on factor type
+        let mut user_verified = false;
         let success = match challenge.factor_type {
             MfaFactorType::Totp => self.verify_totp(&challenge.user_id, response),
-            MfaFactorType::WebAuthn => self.verify_webauthn(&challenge, response).await,
+            MfaFactorType::WebAuthn => match self.verify_webauthn(&challenge, response) {
+                Some(outcome) => {
+                    user_verified = outcome.user_verified;
+                    true
+                }
+                None => false,
+            },
             MfaFactorType::Push => self.verify_push(&challenge, response).await,
             MfaFactorType::Sms | MfaFactorType::Email => {
                 challenge.metadata.get("code") == Some(&response.to_string())
@@ -169,6 +305,8 @@ impl MfaEngine {
             success,
             factor_type: challenge.factor_type,
             message: if success { None } else { Some("Verification failed".to_string()) },
+            phishing_resistant: success && challenge.factor_type.is_phishing_resistant(),
+            user_verified,
         }
     }
     
@@ -185,9 +323,32 @@ impl MfaEngine {
         false
     }
     
-    async fn verify_webauthn(&self, _challenge: &MfaChallenge, _response: &str) -> bool {
-        // In production: WebAuthn verification
-        true
+    /// `response` is the JSON-serialized `PublicKeyCredential` from
+    /// navigator.credentials.get
+    fn verify_webauthn(&self, challenge: &MfaChallenge, response: &str) -> Option<webauthn::AssertionOutcome> {
+        let manager = self.webauthn.as_ref()?;
+        let assertion: webauthn::AssertionResponse = serde_json::from_str(response).ok()?;
+        
+        match manager.finish_assertion(&assertion) {
+            // The assertion must answer this MFA challenge, for this user
+            Ok(outcome) if outcome.user_id == challenge.user_id
+                && challenge.metadata.get("webauthn_challenge") == Some(&outcome.challenge) =>
+            {
+                if let Some(mut factors) = self.user_factors.get_mut(&challenge.user_id) {
+                    if let Some(factor) = factors.iter_mut().find(|f| {
+                        f.metadata.get("credential_id") == Some(&outcome.credential_id)
+                    }) {
+                        factor.last_used = Some(chrono::Utc::now());
+                    }
+                }
+                Some(outcome)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("WebAuthn assertion for {} rejected: {}", challenge.user_id, e);
+                None
+            }
+        }
     }
     
     async fn verify_push(&self, _challenge: &MfaChallenge, _response: &str) -> bool {
@@ -256,6 +417,22 @@ impl TotpValidator {
     }
 }
 
+fn webauthn_factor(credential: &webauthn::WebAuthnCredential, name: &str) -> MfaFactor {
+    let mut metadata = HashMap::new();
+    metadata.insert("credential_id".to_string(), credential.id_b64());
+    metadata.insert("aaguid".to_string(), credential.aaguid.to_string());
+    metadata.insert("passkey".to_string(), credential.backup_eligible.to_string());
+    
+    MfaFactor {
+        id: uuid::Uuid::new_v4().to_string(),
+        factor_type: MfaFactorType::WebAuthn,
+        name: name.to_string(),
+        registered_at: credential.created_at,
+        last_used: credential.last_used,
+        metadata,
+    }
+}
+
 fn generate_otp_code() -> String {
     use rand::Rng;
     let mut rng = rand::thread_rng();
@@ -268,6 +445,8 @@ pub enum MfaError {
     FactorNotRegistered,
     ChallengeFailed,
     ChallengeExpired,
+    WebAuthnDisabled,
+    WebAuthn(webauthn::WebAuthnError),
 }
 
 impl std::fmt::Display for MfaError {
@@ -277,6 +456,8 @@ impl std::fmt::Display for MfaError {
             Self::FactorNotRegistered => write!(f, "Factor not registered"),
             Self::ChallengeFailed => write!(f, "Challenge failed"),
             Self::ChallengeExpired => write!(f, "Challenge expired"),
+            Self::WebAuthnDisabled => write!(f, "WebAuthn is not configured"),
+            Self::WebAuthn(e) => write!(f, "WebAuthn: {}", e),
         }
     }
 }
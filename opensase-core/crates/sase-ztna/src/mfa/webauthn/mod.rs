@@ -0,0 +1,1333 @@
+//! WebAuthn / FIDO2
+//!
+//! Registration and assertion ceremonies for passkeys and security keys
+//! (W3C WebAuthn Level 2, §7.1 and §7.2). Challenges are single-use and
+//! bound to the user and ceremony that requested them. Credentials remember
+//! the tenant they were enrolled under so per-tenant authenticator
+//! allowlists apply both at enrolment and at every sign-in. Registrations,
+//! counter advances and removals write through to an optional
+//! [`CredentialStore`] so passkeys and clone detection survive restarts.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64url;
+use base64::Engine;
+use ciborium::value::Value as Cbor;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+pub mod store;
+
+pub use store::{CredentialStore, FileCredentialStore, MemoryCredentialStore};
+
+/// COSE algorithm identifiers accepted for credentials, in preference order
+pub const COSE_ES256: i64 = -7;
+pub const COSE_EDDSA: i64 = -8;
+pub const COSE_RS256: i64 = -257;
+
+// Authenticator data flags
+const FLAG_UP: u8 = 0x01;
+const FLAG_UV: u8 = 0x04;
+const FLAG_BE: u8 = 0x08;
+const FLAG_BS: u8 = 0x10;
+const FLAG_AT: u8 = 0x40;
+
+/// id-fido-gen-ce-aaguid certificate extension
+const AAGUID_EXTENSION_OID: &str = "1.3.6.1.4.1.45724.1.1.4";
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserVerification {
+    Required,
+    Preferred,
+    Discouraged,
+}
+
+/// How much attestation to request and trust
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationPolicy {
+    /// Request none; AAGUIDs are self-reported and untrusted (passkeys)
+    None,
+    /// Request attestation and verify `packed` and `fido-u2f` statements;
+    /// only those chaining to [`WebAuthnConfig::attestation_roots`] count
+    /// as attested, anything else is accepted as unattested
+    Indirect,
+    /// Require a `packed` or `fido-u2f` statement chaining to a root
+    Direct,
+}
+
+impl AttestationPolicy {
+    fn conveyance(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Indirect => "indirect",
+            Self::Direct => "direct",
+        }
+    }
+}
+
+/// Relying party configuration
+#[derive(Debug, Clone)]
+pub struct WebAuthnConfig {
+    /// RP ID: the registrable domain credentials are scoped to
+    pub rp_id: String,
+    pub rp_name: String,
+    /// Exact origins allowed to run ceremonies
+    pub origins: Vec<String>,
+    pub challenge_ttl: chrono::Duration,
+    pub user_verification: UserVerification,
+    pub attestation: AttestationPolicy,
+    /// Ask for discoverable credentials (passkeys)
+    pub prefer_resident_key: bool,
+    /// DER certificates trusted to vouch for authenticator models, e.g.
+    /// from the FIDO Metadata Service; empty trusts no attestation
+    pub attestation_roots: Vec<Vec<u8>>,
+}
+
+impl Default for WebAuthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: "localhost".to_string(),
+            rp_name: "OpenSASE".to_string(),
+            origins: vec!["https://localhost".to_string()],
+            challenge_ttl: chrono::Duration::minutes(5),
+            user_verification: UserVerification::Preferred,
+            attestation: AttestationPolicy::None,
+            prefer_resident_key: true,
+            attestation_roots: Vec::new(),
+        }
+    }
+}
+
+/// Per-tenant restrictions on which authenticators may be used.
+///
+/// An AAGUID is only as trustworthy as the attestation that carried it, so
+/// an allowlist also rejects credentials whose attestation doesn't chain to
+/// [`WebAuthnConfig::attestation_roots`]; pair it with
+/// [`AttestationPolicy::Indirect`] or [`AttestationPolicy::Direct`].
+#[derive(Debug, Clone, Default)]
+pub struct AuthenticatorPolicy {
+    /// Permitted authenticator models; empty allows any
+    pub allowed_aaguids: HashSet<uuid::Uuid>,
+    /// Require user verification (PIN or biometric) whatever the RP default
+    pub require_user_verification: bool,
+    /// Refuse synced passkeys (backup-eligible credentials)
+    pub device_bound_only: bool,
+}
+
+// =============================================================================
+// Credentials
+// =============================================================================
+
+/// Credential public key decoded from COSE
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "alg", rename_all = "lowercase")]
+pub enum CosePublicKey {
+    Es256 { x: Vec<u8>, y: Vec<u8> },
+    EdDsa { x: Vec<u8> },
+    Rs256 { n: Vec<u8>, e: Vec<u8> },
+}
+
+impl CosePublicKey {
+    pub fn alg(&self) -> i64 {
+        match self {
+            Self::Es256 { .. } => COSE_ES256,
+            Self::EdDsa { .. } => COSE_EDDSA,
+            Self::Rs256 { .. } => COSE_RS256,
+        }
+    }
+
+    /// Decode a COSE_Key map (RFC 9052 §7)
+    fn from_cbor(value: &Cbor) -> Result<Self, WebAuthnError> {
+        let map = value.as_map().ok_or(WebAuthnError::Malformed("COSE key is not a map"))?;
+        let int = |label: i64| map.iter()
+            .find(|(k, _)| k.as_integer().map(i128::from) == Some(label as i128))
+            .map(|(_, v)| v);
+        let bytes = |label: i64| int(label)
+            .and_then(|v| v.as_bytes())
+            .cloned()
+            .ok_or(WebAuthnError::Malformed("COSE key parameter missing"));
+        let number = |label: i64| int(label)
+            .and_then(|v| v.as_integer())
+            .map(i128::from);
+
+        match (number(1), number(3)) {
+            // kty EC2, crv P-256
+            (Some(2), Some(alg)) if alg == COSE_ES256 as i128 && number(-1) == Some(1) => {
+                let (x, y) = (bytes(-2)?, bytes(-3)?);
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(WebAuthnError::Malformed("P-256 coordinate length"));
+                }
+                Ok(Self::Es256 { x, y })
+            }
+            // kty OKP, crv Ed25519
+            (Some(1), Some(alg)) if alg == COSE_EDDSA as i128 && number(-1) == Some(6) => {
+                let x = bytes(-2)?;
+                if x.len() != 32 {
+                    return Err(WebAuthnError::Malformed("Ed25519 key length"));
+                }
+                Ok(Self::EdDsa { x })
+            }
+            // kty RSA
+            (Some(3), Some(alg)) if alg == COSE_RS256 as i128 => {
+                Ok(Self::Rs256 { n: bytes(-1)?, e: bytes(-2)? })
+            }
+            (_, Some(alg)) => Err(WebAuthnError::UnsupportedAlgorithm(alg as i64)),
+            _ => Err(WebAuthnError::Malformed("COSE key without kty/alg")),
+        }
+    }
+
+    /// Verify a signature as produced by an authenticator for this key
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        use ring::signature;
+
+        match self {
+            Self::Es256 { x, y } => {
+                let mut point = Vec::with_capacity(65);
+                point.push(0x04);
+                point.extend_from_slice(x);
+                point.extend_from_slice(y);
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            Self::EdDsa { x } => {
+                signature::UnparsedPublicKey::new(&signature::ED25519, x)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            Self::Rs256 { n, e } => {
+                signature::RsaPublicKeyComponents { n, e }
+                    .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                    .is_ok()
+            }
+        }
+    }
+
+    /// Uncompressed P-256 point, as fido-u2f signs over it
+    fn uncompressed_point(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::Es256 { x, y } => Some([&[0x04][..], x.as_slice(), y.as_slice()].concat()),
+            _ => None,
+        }
+    }
+}
+
+/// A registered WebAuthn credential
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebAuthnCredential {
+    pub credential_id: Vec<u8>,
+    pub user_id: String,
+    pub tenant_id: String,
+    pub public_key: CosePublicKey,
+    pub sign_count: u32,
+    pub aaguid: uuid::Uuid,
+    pub attestation_format: String,
+    /// The attestation statement verified, so `aaguid` can be trusted
+    pub attested: bool,
+    /// Synced passkey rather than a device-bound key
+    pub backup_eligible: bool,
+    pub transports: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl WebAuthnCredential {
+    /// Credential ID as it appears on the wire
+    pub fn id_b64(&self) -> String {
+        b64url.encode(&self.credential_id)
+    }
+}
+
+// =============================================================================
+// Wire Types (WebAuthn JSON serialization)
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    /// Opaque user handle (base64url); never the raw user ID
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialParameter {
+    #[serde(rename = "type")]
+    pub cred_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub cred_type: String,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transports: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub require_resident_key: bool,
+    pub user_verification: UserVerification,
+}
+
+/// `PublicKeyCredentialCreationOptions` for `navigator.credentials.create`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameter>,
+    pub timeout: u64,
+    pub attestation: String,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+/// `PublicKeyCredentialRequestOptions` for `navigator.credentials.get`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: UserVerification,
+    pub timeout: u64,
+}
+
+/// Result of `navigator.credentials.create`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegistrationResponse {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+/// Result of `navigator.credentials.get`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionResponse {
+    pub id: String,
+    pub response: AssertionResponseData,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponseData {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+    #[serde(default, rename = "crossOrigin")]
+    cross_origin: bool,
+}
+
+/// Verified assertion
+#[derive(Debug, Clone)]
+pub struct AssertionOutcome {
+    pub user_id: String,
+    pub credential_id: String,
+    /// The challenge this assertion answered
+    pub challenge: String,
+    pub user_verified: bool,
+    pub backed_up: bool,
+    pub sign_count: u32,
+}
+
+// =============================================================================
+// Authenticator Data
+// =============================================================================
+
+/// Parsed authenticator data (WebAuthn §6.1)
+struct AuthenticatorData {
+    rp_id_hash: [u8; 32],
+    flags: u8,
+    sign_count: u32,
+    attested: Option<AttestedCredential>,
+}
+
+struct AttestedCredential {
+    aaguid: uuid::Uuid,
+    credential_id: Vec<u8>,
+    public_key: CosePublicKey,
+}
+
+impl AuthenticatorData {
+    fn parse(data: &[u8]) -> Result<Self, WebAuthnError> {
+        if data.len() < 37 {
+            return Err(WebAuthnError::Malformed("authenticator data too short"));
+        }
+
+        let mut rp_id_hash = [0u8; 32];
+        rp_id_hash.copy_from_slice(&data[..32]);
+        let flags = data[32];
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        let attested = if flags & FLAG_AT != 0 {
+            let rest = &data[37..];
+            if rest.len() < 18 {
+                return Err(WebAuthnError::Malformed("attested credential data too short"));
+            }
+            let aaguid = uuid::Uuid::from_slice(&rest[..16])
+                .map_err(|_| WebAuthnError::Malformed("AAGUID"))?;
+            let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            let rest = &rest[18..];
+            if rest.len() < id_len {
+                return Err(WebAuthnError::Malformed("credential ID length"));
+            }
+            let credential_id = rest[..id_len].to_vec();
+
+            // The key is followed by extensions when ED is set; decoding one
+            // item from the slice leaves those unread
+            let mut key_bytes = &rest[id_len..];
+            let key: Cbor = ciborium::de::from_reader(&mut key_bytes)
+                .map_err(|_| WebAuthnError::Malformed("credential public key"))?;
+
+            Some(AttestedCredential {
+                aaguid,
+                credential_id,
+                public_key: CosePublicKey::from_cbor(&key)?,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self { rp_id_hash, flags, sign_count, attested })
+    }
+
+    fn user_present(&self) -> bool {
+        self.flags & FLAG_UP != 0
+    }
+
+    fn user_verified(&self) -> bool {
+        self.flags & FLAG_UV != 0
+    }
+
+    fn backup_eligible(&self) -> bool {
+        self.flags & FLAG_BE != 0
+    }
+
+    fn backed_up(&self) -> bool {
+        self.flags & FLAG_BS != 0
+    }
+}
+
+// =============================================================================
+// Relying Party
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ceremony {
+    Registration,
+    Assertion,
+}
+
+#[derive(Debug, Clone)]
+struct PendingChallenge {
+    ceremony: Ceremony,
+    user_id: String,
+    tenant_id: String,
+    require_uv: bool,
+    /// Credentials the assertion may use (empty for registration)
+    allowed: Vec<String>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// WebAuthn relying party: challenge storage, ceremony verification and
+/// the credential registry
+pub struct WebAuthnManager {
+    config: WebAuthnConfig,
+    /// Outstanding challenges keyed by their base64url value
+    challenges: dashmap::DashMap<String, PendingChallenge>,
+    /// Credentials keyed by base64url credential ID
+    credentials: dashmap::DashMap<String, WebAuthnCredential>,
+    tenant_policies: dashmap::DashMap<String, AuthenticatorPolicy>,
+    /// Durable copy of `credentials`; absent for in-memory only
+    store: Option<Arc<dyn CredentialStore>>,
+}
+
+impl WebAuthnManager {
+    pub fn new(config: WebAuthnConfig) -> Self {
+        Self {
+            config,
+            challenges: dashmap::DashMap::new(),
+            credentials: dashmap::DashMap::new(),
+            tenant_policies: dashmap::DashMap::new(),
+            store: None,
+        }
+    }
+
+    /// Persist credentials to `store`, loading any it already holds
+    pub fn with_store(mut self, store: Arc<dyn CredentialStore>) -> Result<Self, WebAuthnError> {
+        for credential in store.load()? {
+            self.credentials.insert(credential.id_b64(), credential);
+        }
+        tracing::info!("Loaded {} WebAuthn credentials", self.credentials.len());
+
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    pub fn config(&self) -> &WebAuthnConfig {
+        &self.config
+    }
+
+    /// Set a tenant's authenticator policy; applies to existing credentials
+    /// at their next sign-in
+    pub fn set_tenant_policy(&self, tenant_id: &str, policy: AuthenticatorPolicy) {
+        self.tenant_policies.insert(tenant_id.to_string(), policy);
+    }
+
+    pub fn tenant_policy(&self, tenant_id: &str) -> AuthenticatorPolicy {
+        self.tenant_policies.get(tenant_id).map(|p| p.clone()).unwrap_or_default()
+    }
+
+    /// Credentials registered by a user
+    pub fn credentials_for(&self, user_id: &str) -> Vec<WebAuthnCredential> {
+        self.credentials.iter()
+            .filter(|c| c.user_id == user_id)
+            .map(|c| c.clone())
+            .collect()
+    }
+
+    /// Every registered credential
+    pub fn all_credentials(&self) -> Vec<WebAuthnCredential> {
+        self.credentials.iter().map(|c| c.clone()).collect()
+    }
+
+    pub fn has_credentials(&self, user_id: &str) -> bool {
+        self.credentials.iter().any(|c| c.user_id == user_id)
+    }
+
+    /// Remove a credential (lost key, offboarding)
+    pub fn remove_credential(&self, credential_id: &str) -> Option<WebAuthnCredential> {
+        let removed = self.credentials.remove(credential_id).map(|(_, c)| c);
+        if let (Some(store), Some(_)) = (&self.store, &removed) {
+            // A credential left in the store would come back on restart
+            if let Err(e) = store.remove(credential_id) {
+                tracing::error!("Failed to remove stored WebAuthn credential {}: {}", credential_id, e);
+            }
+        }
+        removed
+    }
+
+    /// Begin enrolling a passkey or security key
+    pub fn start_registration(
+        &self,
+        user_id: &str,
+        user_name: &str,
+        display_name: &str,
+        tenant_id: &str,
+    ) -> RegistrationOptions {
+        let policy = self.tenant_policy(tenant_id);
+        let user_verification = self.effective_uv(&policy);
+        let challenge = self.issue_challenge(PendingChallenge {
+            ceremony: Ceremony::Registration,
+            user_id: user_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            require_uv: user_verification == UserVerification::Required,
+            allowed: Vec::new(),
+            expires_at: chrono::Utc::now() + self.config.challenge_ttl,
+        });
+
+        RegistrationOptions {
+            challenge,
+            rp: RelyingParty {
+                id: self.config.rp_id.clone(),
+                name: self.config.rp_name.clone(),
+            },
+            user: UserEntity {
+                id: b64url.encode(Sha256::digest(user_id.as_bytes())),
+                name: user_name.to_string(),
+                display_name: display_name.to_string(),
+            },
+            pub_key_cred_params: [COSE_ES256, COSE_EDDSA, COSE_RS256].iter()
+                .map(|alg| CredentialParameter { cred_type: "public-key".to_string(), alg: *alg })
+                .collect(),
+            timeout: self.timeout_ms(),
+            attestation: self.config.attestation.conveyance().to_string(),
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: if self.config.prefer_resident_key { "preferred" } else { "discouraged" }.to_string(),
+                require_resident_key: false,
+                user_verification,
+            },
+            exclude_credentials: self.descriptors(user_id),
+        }
+    }
+
+    /// Verify an attestation and store the new credential (WebAuthn §7.1)
+    pub fn finish_registration(&self, response: &RegistrationResponse) -> Result<WebAuthnCredential, WebAuthnError> {
+        let client_data_bytes = decode(&response.response.client_data_json)?;
+        let client_data = self.check_client_data(&client_data_bytes, "webauthn.create")?;
+        let pending = self.take_challenge(&client_data.challenge, Ceremony::Registration)?;
+
+        let attestation = decode(&response.response.attestation_object)?;
+        let attestation: Cbor = ciborium::de::from_reader(attestation.as_slice())
+            .map_err(|_| WebAuthnError::Malformed("attestation object"))?;
+        let fmt = map_get(&attestation, "fmt")
+            .and_then(|v| v.as_text())
+            .ok_or(WebAuthnError::Malformed("attestation format"))?
+            .to_string();
+        let att_stmt = map_get(&attestation, "attStmt")
+            .ok_or(WebAuthnError::Malformed("attestation statement"))?;
+        let auth_data_bytes = map_get(&attestation, "authData")
+            .and_then(|v| v.as_bytes())
+            .ok_or(WebAuthnError::Malformed("authenticator data"))?;
+
+        let auth_data = AuthenticatorData::parse(auth_data_bytes)?;
+        self.check_flags(&auth_data, pending.require_uv)?;
+        let credential = auth_data.attested.as_ref()
+            .ok_or(WebAuthnError::Malformed("no attested credential data"))?;
+
+        let policy = self.tenant_policy(&pending.tenant_id);
+        if policy.device_bound_only && auth_data.backup_eligible() {
+            return Err(WebAuthnError::AuthenticatorNotAllowed);
+        }
+
+        let client_data_hash = Sha256::digest(&client_data_bytes);
+        let attested = self.verify_attestation(&fmt, att_stmt, auth_data_bytes, &client_data_hash, credential)?;
+
+        if !policy.allowed_aaguids.is_empty()
+            && !(attested && policy.allowed_aaguids.contains(&credential.aaguid))
+        {
+            return Err(WebAuthnError::AuthenticatorNotAllowed);
+        }
+
+        let id = b64url.encode(&credential.credential_id);
+        if self.credentials.contains_key(&id) {
+            return Err(WebAuthnError::CredentialExists);
+        }
+
+        let stored = WebAuthnCredential {
+            credential_id: credential.credential_id.clone(),
+            user_id: pending.user_id,
+            tenant_id: pending.tenant_id,
+            public_key: credential.public_key.clone(),
+            sign_count: auth_data.sign_count,
+            aaguid: credential.aaguid,
+            attestation_format: fmt,
+            attested,
+            backup_eligible: auth_data.backup_eligible(),
+            transports: response.response.transports.clone(),
+            created_at: chrono::Utc::now(),
+            last_used: None,
+        };
+        if let Some(store) = &self.store {
+            store.save(&stored)?;
+        }
+        self.credentials.insert(id, stored.clone());
+
+        tracing::info!(
+            "Registered WebAuthn credential for {} (aaguid {}, attested: {})",
+            stored.user_id, stored.aaguid, stored.attested
+        );
+
+        Ok(stored)
+    }
+
+    /// Begin a sign-in with one of the user's credentials
+    pub fn start_assertion(&self, user_id: &str) -> Result<AssertionOptions, WebAuthnError> {
+        let credentials = self.credentials_for(user_id);
+        let Some(first) = credentials.first() else {
+            return Err(WebAuthnError::NoCredentials);
+        };
+
+        let policy = self.tenant_policy(&first.tenant_id);
+        let user_verification = self.effective_uv(&policy);
+        let allow_credentials = self.descriptors(user_id);
+        let challenge = self.issue_challenge(PendingChallenge {
+            ceremony: Ceremony::Assertion,
+            user_id: user_id.to_string(),
+            tenant_id: first.tenant_id.clone(),
+            require_uv: user_verification == UserVerification::Required,
+            allowed: allow_credentials.iter().map(|d| d.id.clone()).collect(),
+            expires_at: chrono::Utc::now() + self.config.challenge_ttl,
+        });
+
+        Ok(AssertionOptions {
+            challenge,
+            rp_id: self.config.rp_id.clone(),
+            allow_credentials,
+            user_verification,
+            timeout: self.timeout_ms(),
+        })
+    }
+
+    /// Verify an assertion and advance the credential's counter (WebAuthn §7.2)
+    pub fn finish_assertion(&self, response: &AssertionResponse) -> Result<AssertionOutcome, WebAuthnError> {
+        let client_data_bytes = decode(&response.response.client_data_json)?;
+        let client_data = self.check_client_data(&client_data_bytes, "webauthn.get")?;
+        let pending = self.take_challenge(&client_data.challenge, Ceremony::Assertion)?;
+
+        // Normalise padding/alphabet differences before comparing IDs
+        let credential_id = b64url.encode(decode(&response.id)?);
+        if !pending.allowed.contains(&credential_id) {
+            return Err(WebAuthnError::UnknownCredential);
+        }
+
+        let auth_data_bytes = decode(&response.response.authenticator_data)?;
+        let auth_data = AuthenticatorData::parse(&auth_data_bytes)?;
+
+        let mut credential = self.credentials.get_mut(&credential_id)
+            .ok_or(WebAuthnError::UnknownCredential)?;
+        if credential.user_id != pending.user_id {
+            return Err(WebAuthnError::UnknownCredential);
+        }
+
+        let policy = self.tenant_policy(&credential.tenant_id);
+        self.check_flags(&auth_data, pending.require_uv || policy.require_user_verification)?;
+
+        let signed = [auth_data_bytes.as_slice(), Sha256::digest(&client_data_bytes).as_slice()].concat();
+        if !credential.public_key.verify(&signed, &decode(&response.response.signature)?) {
+            return Err(WebAuthnError::InvalidSignature);
+        }
+
+        // Tenant policy may have tightened since enrolment
+        if (!policy.allowed_aaguids.is_empty()
+            && !(credential.attested && policy.allowed_aaguids.contains(&credential.aaguid)))
+            || (policy.device_bound_only && credential.backup_eligible)
+        {
+            return Err(WebAuthnError::AuthenticatorNotAllowed);
+        }
+
+        // A counter that fails to advance means the key was cloned
+        if (auth_data.sign_count != 0 || credential.sign_count != 0)
+            && auth_data.sign_count <= credential.sign_count
+        {
+            tracing::warn!(
+                "WebAuthn counter regression for credential {} of {}: {} <= {}",
+                credential_id, credential.user_id, auth_data.sign_count, credential.sign_count
+            );
+            return Err(WebAuthnError::CounterRegression);
+        }
+
+        credential.sign_count = auth_data.sign_count;
+        credential.last_used = Some(chrono::Utc::now());
+        if let Some(store) = &self.store {
+            // A stale stored counter only weakens clone detection after a
+            // restart; the sign-in itself verified
+            if let Err(e) = store.save(&credential) {
+                tracing::warn!("Failed to persist WebAuthn counter for {}: {}", credential_id, e);
+            }
+        }
+
+        Ok(AssertionOutcome {
+            user_id: credential.user_id.clone(),
+            credential_id,
+            challenge: client_data.challenge,
+            user_verified: auth_data.user_verified(),
+            backed_up: auth_data.backed_up(),
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    /// Drop challenges that were never answered
+    pub fn cleanup_expired(&self) -> usize {
+        let now = chrono::Utc::now();
+        let before = self.challenges.len();
+        self.challenges.retain(|_, c| c.expires_at > now);
+        before - self.challenges.len()
+    }
+
+    fn issue_challenge(&self, pending: PendingChallenge) -> String {
+        use rand::RngCore;
+
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let challenge = b64url.encode(bytes);
+        self.challenges.insert(challenge.clone(), pending);
+        challenge
+    }
+
+    /// Consume a challenge; each is good for exactly one ceremony attempt
+    fn take_challenge(&self, challenge: &str, ceremony: Ceremony) -> Result<PendingChallenge, WebAuthnError> {
+        let (_, pending) = self.challenges.remove(challenge)
+            .ok_or(WebAuthnError::UnknownChallenge)?;
+
+        if pending.ceremony != ceremony {
+            return Err(WebAuthnError::UnknownChallenge);
+        }
+        if chrono::Utc::now() > pending.expires_at {
+            return Err(WebAuthnError::ChallengeExpired);
+        }
+        Ok(pending)
+    }
+
+    fn check_client_data(&self, bytes: &[u8], ceremony: &str) -> Result<CollectedClientData, WebAuthnError> {
+        let client_data: CollectedClientData = serde_json::from_slice(bytes)
+            .map_err(|_| WebAuthnError::Malformed("client data"))?;
+
+        if client_data.ceremony != ceremony {
+            return Err(WebAuthnError::Malformed("client data type"));
+        }
+        if client_data.cross_origin || !self.config.origins.iter().any(|o| o == &client_data.origin) {
+            return Err(WebAuthnError::OriginMismatch(client_data.origin));
+        }
+        Ok(client_data)
+    }
+
+    fn check_flags(&self, auth_data: &AuthenticatorData, require_uv: bool) -> Result<(), WebAuthnError> {
+        if auth_data.rp_id_hash[..] != Sha256::digest(self.config.rp_id.as_bytes())[..] {
+            return Err(WebAuthnError::RpIdMismatch);
+        }
+        if !auth_data.user_present() {
+            return Err(WebAuthnError::UserNotPresent);
+        }
+        if require_uv && !auth_data.user_verified() {
+            return Err(WebAuthnError::UserNotVerified);
+        }
+        Ok(())
+    }
+
+    /// Verify the attestation statement; `Ok(true)` when it chains to a
+    /// trusted root and so proves the authenticator model
+    fn verify_attestation(
+        &self,
+        fmt: &str,
+        att_stmt: &Cbor,
+        auth_data: &[u8],
+        client_data_hash: &[u8],
+        credential: &AttestedCredential,
+    ) -> Result<bool, WebAuthnError> {
+        if self.config.attestation == AttestationPolicy::None {
+            // Nothing was asked for, so whatever came back proves nothing
+            return Ok(false);
+        }
+
+        let verified = match fmt {
+            "none" => false,
+            "packed" => {
+                verify_packed(att_stmt, auth_data, client_data_hash, credential)?
+                    && chains_to_root(att_stmt, &self.config.attestation_roots)?
+            }
+            "fido-u2f" => {
+                verify_fido_u2f(att_stmt, auth_data, client_data_hash, credential)?;
+                chains_to_root(att_stmt, &self.config.attestation_roots)?
+            }
+            other if self.config.attestation == AttestationPolicy::Direct => {
+                return Err(WebAuthnError::UnsupportedAttestation(other.to_string()));
+            }
+            _ => false,
+        };
+
+        if !verified && self.config.attestation == AttestationPolicy::Direct {
+            return Err(WebAuthnError::AttestationRequired);
+        }
+        Ok(verified)
+    }
+
+    fn effective_uv(&self, policy: &AuthenticatorPolicy) -> UserVerification {
+        if policy.require_user_verification {
+            UserVerification::Required
+        } else {
+            self.config.user_verification
+        }
+    }
+
+    fn descriptors(&self, user_id: &str) -> Vec<CredentialDescriptor> {
+        self.credentials_for(user_id).iter()
+            .map(|c| CredentialDescriptor {
+                cred_type: "public-key".to_string(),
+                id: c.id_b64(),
+                transports: c.transports.clone(),
+            })
+            .collect()
+    }
+
+    fn timeout_ms(&self) -> u64 {
+        self.config.challenge_ttl.num_milliseconds().max(0) as u64
+    }
+}
+
+// =============================================================================
+// Attestation Formats
+// =============================================================================
+
+/// `packed` (WebAuthn §8.2): self attestation, or a batch certificate whose
+/// AAGUID extension, when present, must match the authenticator data.
+/// `Ok(true)` when signed by a batch certificate, which the caller still
+/// has to chain to a root; self attestation proves nothing about the model.
+fn verify_packed(
+    att_stmt: &Cbor,
+    auth_data: &[u8],
+    client_data_hash: &[u8],
+    credential: &AttestedCredential,
+) -> Result<bool, WebAuthnError> {
+    let alg = map_get(att_stmt, "alg")
+        .and_then(|v| v.as_integer())
+        .map(i128::from)
+        .ok_or(WebAuthnError::Malformed("packed alg"))?;
+    let sig = map_get(att_stmt, "sig")
+        .and_then(|v| v.as_bytes())
+        .ok_or(WebAuthnError::Malformed("packed sig"))?;
+    let signed = [auth_data, client_data_hash].concat();
+
+    match leaf_certificate(att_stmt) {
+        Some(der) => {
+            if alg != COSE_ES256 as i128 {
+                return Err(WebAuthnError::UnsupportedAlgorithm(alg as i64));
+            }
+            let (_, cert) = x509_parser::parse_x509_certificate(der)
+                .map_err(|_| WebAuthnError::Malformed("attestation certificate"))?;
+
+            for ext in cert.extensions() {
+                if ext.oid.to_id_string() == AAGUID_EXTENSION_OID {
+                    // OCTET STRING wrapping the 16-byte AAGUID
+                    let value = ext.value;
+                    if value.len() != 18 || value[..2] != [0x04, 0x10] || value[2..] != credential.aaguid.as_bytes()[..] {
+                        return Err(WebAuthnError::AttestationInvalid);
+                    }
+                }
+            }
+
+            verify_certificate_signature(&cert, &signed, sig)?;
+            Ok(true)
+        }
+        None => {
+            if alg != credential.public_key.alg() as i128 {
+                return Err(WebAuthnError::AttestationInvalid);
+            }
+            if credential.public_key.verify(&signed, sig) {
+                Ok(false)
+            } else {
+                Err(WebAuthnError::AttestationInvalid)
+            }
+        }
+    }
+}
+
+/// `fido-u2f` (WebAuthn §8.6): legacy U2F keys, P-256 only
+fn verify_fido_u2f(
+    att_stmt: &Cbor,
+    auth_data: &[u8],
+    client_data_hash: &[u8],
+    credential: &AttestedCredential,
+) -> Result<(), WebAuthnError> {
+    let sig = map_get(att_stmt, "sig")
+        .and_then(|v| v.as_bytes())
+        .ok_or(WebAuthnError::Malformed("fido-u2f sig"))?;
+    let der = leaf_certificate(att_stmt).ok_or(WebAuthnError::Malformed("fido-u2f x5c"))?;
+    let (_, cert) = x509_parser::parse_x509_certificate(der)
+        .map_err(|_| WebAuthnError::Malformed("attestation certificate"))?;
+    let public_key = credential.public_key.uncompressed_point()
+        .ok_or(WebAuthnError::UnsupportedAlgorithm(credential.public_key.alg()))?;
+
+    let signed = [
+        &[0x00][..],
+        &auth_data[..32],
+        client_data_hash,
+        &credential.credential_id,
+        &public_key,
+    ].concat();
+
+    verify_certificate_signature(&cert, &signed, sig)
+}
+
+fn verify_certificate_signature(
+    cert: &x509_parser::certificate::X509Certificate<'_>,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), WebAuthnError> {
+    use ring::signature;
+
+    let point = cert.public_key().subject_public_key.data.as_ref();
+    signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+        .verify(message, signature)
+        .map_err(|_| WebAuthnError::AttestationInvalid)
+}
+
+/// Whether `x5c` leads from the attestation certificate to one of `roots`:
+/// every certificate current, each signed by the next, and the last one
+/// signed by a root (a self-signed root sent in `x5c` signs itself)
+fn chains_to_root(att_stmt: &Cbor, roots: &[Vec<u8>]) -> Result<bool, WebAuthnError> {
+    use x509_parser::certificate::X509Certificate;
+
+    let chain = map_get(att_stmt, "x5c")
+        .and_then(|v| v.as_array())
+        .ok_or(WebAuthnError::Malformed("x5c"))?
+        .iter()
+        .map(|c| {
+            let der = c.as_bytes().ok_or(WebAuthnError::Malformed("x5c"))?;
+            x509_parser::parse_x509_certificate(der)
+                .map(|(_, cert)| cert)
+                .map_err(|_| WebAuthnError::Malformed("attestation certificate"))
+        })
+        .collect::<Result<Vec<X509Certificate<'_>>, _>>()?;
+    let roots: Vec<X509Certificate<'_>> = roots.iter()
+        .filter_map(|der| x509_parser::parse_x509_certificate(der).ok())
+        .map(|(_, root)| root)
+        .filter(|root| root.is_ca() && root.validity().is_valid())
+        .collect();
+
+    let Some(last) = chain.last() else {
+        return Ok(false);
+    };
+    if chain.iter().any(|cert| !cert.validity().is_valid()) {
+        return Ok(false);
+    }
+    let linked = chain.windows(2).all(|pair| pair[1].is_ca() && signed_by(&pair[0], &pair[1]));
+    let anchored = roots.iter().any(|root| signed_by(last, root));
+    Ok(linked && anchored)
+}
+
+/// Whether `issuer` named and signed `cert`
+fn signed_by(
+    cert: &x509_parser::certificate::X509Certificate<'_>,
+    issuer: &x509_parser::certificate::X509Certificate<'_>,
+) -> bool {
+    use ring::signature;
+
+    if cert.issuer().as_raw() != issuer.subject().as_raw() {
+        return false;
+    }
+    let key = issuer.public_key().subject_public_key.data.as_ref();
+    let algorithm: &dyn signature::VerificationAlgorithm = match (
+        cert.signature_algorithm.algorithm.to_id_string().as_str(),
+        key.len(),
+    ) {
+        ("1.2.840.10045.4.3.2", 65) => &signature::ECDSA_P256_SHA256_ASN1,
+        ("1.2.840.10045.4.3.3", 65) => &signature::ECDSA_P256_SHA384_ASN1,
+        ("1.2.840.10045.4.3.2", 97) => &signature::ECDSA_P384_SHA256_ASN1,
+        ("1.2.840.10045.4.3.3", 97) => &signature::ECDSA_P384_SHA384_ASN1,
+        ("1.2.840.113549.1.1.11", _) => &signature::RSA_PKCS1_2048_8192_SHA256,
+        ("1.2.840.113549.1.1.12", _) => &signature::RSA_PKCS1_2048_8192_SHA384,
+        _ => return false,
+    };
+    signature::UnparsedPublicKey::new(algorithm, key)
+        .verify(cert.tbs_certificate.as_ref(), cert.signature_value.data.as_ref())
+        .is_ok()
+}
+
+fn leaf_certificate(att_stmt: &Cbor) -> Option<&[u8]> {
+    map_get(att_stmt, "x5c")?
+        .as_array()?
+        .first()?
+        .as_bytes()
+        .map(Vec::as_slice)
+}
+
+fn map_get<'a>(value: &'a Cbor, key: &str) -> Option<&'a Cbor> {
+    value.as_map()?
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+/// Browsers emit unpadded base64url; tolerate padding from other clients
+fn decode(value: &str) -> Result<Vec<u8>, WebAuthnError> {
+    b64url.decode(value.trim_end_matches('='))
+        .map_err(|_| WebAuthnError::Malformed("base64url"))
+}
+
+#[derive(Debug)]
+pub enum WebAuthnError {
+    Malformed(&'static str),
+    UnknownChallenge,
+    ChallengeExpired,
+    OriginMismatch(String),
+    RpIdMismatch,
+    UserNotPresent,
+    UserNotVerified,
+    UnsupportedAlgorithm(i64),
+    UnsupportedAttestation(String),
+    AttestationRequired,
+    AttestationInvalid,
+    AuthenticatorNotAllowed,
+    CredentialExists,
+    NoCredentials,
+    UnknownCredential,
+    InvalidSignature,
+    CounterRegression,
+    Storage(String),
+}
+
+impl std::fmt::Display for WebAuthnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(what) => write!(f, "Malformed {}", what),
+            Self::UnknownChallenge => write!(f, "Unknown or already used challenge"),
+            Self::ChallengeExpired => write!(f, "Challenge expired"),
+            Self::OriginMismatch(origin) => write!(f, "Origin not allowed: {}", origin),
+            Self::RpIdMismatch => write!(f, "RP ID hash mismatch"),
+            Self::UserNotPresent => write!(f, "User presence not asserted"),
+            Self::UserNotVerified => write!(f, "User verification required"),
+            Self::UnsupportedAlgorithm(alg) => write!(f, "Unsupported COSE algorithm {}", alg),
+            Self::UnsupportedAttestation(fmt) => write!(f, "Unsupported attestation format {}", fmt),
+            Self::AttestationRequired => write!(f, "Verified attestation required"),
+            Self::AttestationInvalid => write!(f, "Attestation statement invalid"),
+            Self::AuthenticatorNotAllowed => write!(f, "Authenticator not allowed for tenant"),
+            Self::CredentialExists => write!(f, "Credential already registered"),
+            Self::NoCredentials => write!(f, "No WebAuthn credentials registered"),
+            Self::UnknownCredential => write!(f, "Unknown credential"),
+            Self::InvalidSignature => write!(f, "Invalid assertion signature"),
+            Self::CounterRegression => write!(f, "Signature counter did not advance; possible cloned authenticator"),
+            Self::Storage(e) => write!(f, "Credential storage: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebAuthnError {}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pki::der::{self, oid};
+    use crate::pki::x509::{self, TbsCertificate};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    const AAGUID: uuid::Uuid = uuid::Uuid::from_bytes([
+        0x2f, 0xc0, 0x57, 0x9f, 0x81, 0x13, 0x47, 0xea, 0xb1, 0x16, 0xbb, 0x5a, 0x8d, 0xb9, 0x20, 0x2a,
+    ]);
+
+    fn key() -> EcdsaKeyPair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap()
+    }
+
+    /// `subject_key`'s certificate, signed by `issuer_key`
+    fn certificate(subject: &str, subject_key: &EcdsaKeyPair, issuer: &str, issuer_key: &EcdsaKeyPair, ca: bool) -> Vec<u8> {
+        let spki = der::sequence(&[
+            &der::sequence(&[&der::oid(oid::EC_PUBLIC_KEY), &der::oid(oid::P256)]),
+            &der::bit_string(subject_key.public_key().as_ref()),
+        ]);
+        let extensions = if ca {
+            vec![x509::extension(oid::BASIC_CONSTRAINTS, true, &der::sequence(&[&der::boolean(true)]))]
+        } else {
+            vec![x509::extension(AAGUID_EXTENSION_OID, false, &der::octet_string(AAGUID.as_bytes()))]
+        };
+        let now = chrono::Utc::now();
+        TbsCertificate {
+            serial: &[0x01],
+            issuer: &x509::name(issuer, Some("Authenticator Vendor")),
+            subject: &x509::name(subject, Some("Authenticator Vendor")),
+            not_before: now - chrono::Duration::hours(1),
+            not_after: now + chrono::Duration::days(1),
+            spki: &spki,
+            extensions,
+        }.sign(issuer_key, &SystemRandom::new()).unwrap()
+    }
+
+    struct Authenticator {
+        credential_key: EcdsaKeyPair,
+        credential_id: Vec<u8>,
+        aaguid: uuid::Uuid,
+    }
+
+    impl Authenticator {
+        fn new(aaguid: uuid::Uuid) -> Self {
+            Self { credential_key: key(), credential_id: b"credential-1".to_vec(), aaguid }
+        }
+
+        fn auth_data(&self) -> Vec<u8> {
+            let point = self.credential_key.public_key().as_ref();
+            let cose = Cbor::Map(vec![
+                (Cbor::Integer(1.into()), Cbor::Integer(2.into())),
+                (Cbor::Integer(3.into()), Cbor::Integer(COSE_ES256.into())),
+                (Cbor::Integer((-1).into()), Cbor::Integer(1.into())),
+                (Cbor::Integer((-2).into()), Cbor::Bytes(point[1..33].to_vec())),
+                (Cbor::Integer((-3).into()), Cbor::Bytes(point[33..].to_vec())),
+            ]);
+
+            let mut data = Sha256::digest(b"localhost").to_vec();
+            data.push(FLAG_UP | FLAG_AT);
+            data.extend_from_slice(&0u32.to_be_bytes());
+            data.extend_from_slice(self.aaguid.as_bytes());
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            ciborium::ser::into_writer(&cose, &mut data).unwrap();
+            data
+        }
+
+        /// Answer a registration with a `packed` statement signed by
+        /// `attestation`, or self attestation without one
+        fn register(
+            &self,
+            manager: &WebAuthnManager,
+            tenant_id: &str,
+            attestation: Option<(&EcdsaKeyPair, Vec<Vec<u8>>)>,
+        ) -> Result<WebAuthnCredential, WebAuthnError> {
+            let options = manager.start_registration("alice", "alice", "Alice", tenant_id);
+            let client_data = serde_json::json!({
+                "type": "webauthn.create",
+                "challenge": options.challenge,
+                "origin": "https://localhost",
+            }).to_string();
+
+            let auth_data = self.auth_data();
+            let signed = [auth_data.as_slice(), Sha256::digest(client_data.as_bytes()).as_slice()].concat();
+            let (signer, x5c) = match attestation {
+                Some((key, chain)) => (key, Some(chain)),
+                None => (&self.credential_key, None),
+            };
+            let sig = signer.sign(&SystemRandom::new(), &signed).unwrap().as_ref().to_vec();
+
+            let mut att_stmt = vec![
+                (Cbor::Text("alg".to_string()), Cbor::Integer(COSE_ES256.into())),
+                (Cbor::Text("sig".to_string()), Cbor::Bytes(sig)),
+            ];
+            if let Some(chain) = x5c {
+                att_stmt.push((Cbor::Text("x5c".to_string()), Cbor::Array(chain.into_iter().map(Cbor::Bytes).collect())));
+            }
+            let object = Cbor::Map(vec![
+                (Cbor::Text("fmt".to_string()), Cbor::Text("packed".to_string())),
+                (Cbor::Text("attStmt".to_string()), Cbor::Map(att_stmt)),
+                (Cbor::Text("authData".to_string()), Cbor::Bytes(auth_data)),
+            ]);
+            let mut attestation_object = Vec::new();
+            ciborium::ser::into_writer(&object, &mut attestation_object).unwrap();
+
+            manager.finish_registration(&RegistrationResponse {
+                id: b64url.encode(&self.credential_id),
+                response: AttestationResponse {
+                    client_data_json: b64url.encode(client_data),
+                    attestation_object: b64url.encode(attestation_object),
+                    transports: vec![],
+                },
+            })
+        }
+    }
+
+    struct Vendor {
+        root_key: EcdsaKeyPair,
+        root: Vec<u8>,
+    }
+
+    impl Vendor {
+        fn new() -> Self {
+            let root_key = key();
+            let root = certificate("Vendor Root", &root_key, "Vendor Root", &root_key, true);
+            Self { root_key, root }
+        }
+
+        /// Batch attestation key and its certificate
+        fn batch(&self) -> (EcdsaKeyPair, Vec<u8>) {
+            let batch_key = key();
+            let cert = certificate("Batch 1", &batch_key, "Vendor Root", &self.root_key, false);
+            (batch_key, cert)
+        }
+    }
+
+    fn relying_party(attestation: AttestationPolicy, roots: Vec<Vec<u8>>) -> WebAuthnManager {
+        WebAuthnManager::new(WebAuthnConfig {
+            attestation,
+            attestation_roots: roots,
+            ..Default::default()
+        })
+    }
+
+    fn allowlist(aaguid: uuid::Uuid) -> AuthenticatorPolicy {
+        AuthenticatorPolicy {
+            allowed_aaguids: [aaguid].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_packed_chained_to_root_is_attested() {
+        let vendor = Vendor::new();
+        let manager = relying_party(AttestationPolicy::Direct, vec![vendor.root.clone()]);
+        manager.set_tenant_policy("acme", allowlist(AAGUID));
+
+        let (batch_key, batch_cert) = vendor.batch();
+        let credential = Authenticator::new(AAGUID)
+            .register(&manager, "acme", Some((&batch_key, vec![batch_cert])))
+            .unwrap();
+        assert!(credential.attested);
+        assert_eq!(credential.aaguid, AAGUID);
+    }
+
+    #[test]
+    fn test_self_signed_packed_is_not_attested() {
+        let vendor = Vendor::new();
+        let rogue_key = key();
+        let rogue_cert = certificate("Batch 1", &rogue_key, "Vendor Root", &rogue_key, false);
+        let authenticator = Authenticator::new(AAGUID);
+
+        // Verifies, but vouches for nothing
+        let manager = relying_party(AttestationPolicy::Indirect, vec![vendor.root.clone()]);
+        let credential = authenticator.register(&manager, "acme", Some((&rogue_key, vec![rogue_cert.clone()]))).unwrap();
+        assert!(!credential.attested);
+
+        let manager = relying_party(AttestationPolicy::Indirect, vec![vendor.root.clone()]);
+        manager.set_tenant_policy("acme", allowlist(AAGUID));
+        assert!(matches!(
+            authenticator.register(&manager, "acme", Some((&rogue_key, vec![rogue_cert.clone()]))),
+            Err(WebAuthnError::AuthenticatorNotAllowed)
+        ));
+
+        let manager = relying_party(AttestationPolicy::Direct, vec![vendor.root.clone()]);
+        assert!(matches!(
+            authenticator.register(&manager, "acme", Some((&rogue_key, vec![rogue_cert]))),
+            Err(WebAuthnError::AttestationRequired)
+        ));
+    }
+
+    #[test]
+    fn test_self_attestation_is_not_attested() {
+        let vendor = Vendor::new();
+        let authenticator = Authenticator::new(AAGUID);
+
+        let manager = relying_party(AttestationPolicy::Indirect, vec![vendor.root.clone()]);
+        assert!(!authenticator.register(&manager, "acme", None).unwrap().attested);
+
+        let manager = relying_party(AttestationPolicy::Indirect, vec![vendor.root.clone()]);
+        manager.set_tenant_policy("acme", allowlist(AAGUID));
+        assert!(matches!(
+            authenticator.register(&manager, "acme", None),
+            Err(WebAuthnError::AuthenticatorNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_aaguid_outside_allowlist_rejected() {
+        let vendor = Vendor::new();
+        let manager = relying_party(AttestationPolicy::Direct, vec![vendor.root.clone()]);
+        manager.set_tenant_policy("acme", allowlist(uuid::Uuid::from_bytes([0x11; 16])));
+
+        let (batch_key, batch_cert) = vendor.batch();
+        assert!(matches!(
+            Authenticator::new(AAGUID).register(&manager, "acme", Some((&batch_key, vec![batch_cert]))),
+            Err(WebAuthnError::AuthenticatorNotAllowed)
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+//! WebAuthn credential storage
+//!
+//! The manager keeps every credential in memory for the ceremonies; a store
+//! is the durable copy it loads at startup and writes through to whenever a
+//! credential is registered, used or removed.
+
+use super::{WebAuthnCredential, WebAuthnError};
+use std::path::{Path, PathBuf};
+
+/// Durable credential registry
+pub trait CredentialStore: Send + Sync {
+    /// Every stored credential, read once at startup
+    fn load(&self) -> Result<Vec<WebAuthnCredential>, WebAuthnError>;
+    /// Insert or replace a credential
+    fn save(&self, credential: &WebAuthnCredential) -> Result<(), WebAuthnError>;
+    /// Remove a credential by base64url ID; removing a missing one is a no-op
+    fn remove(&self, credential_id: &str) -> Result<(), WebAuthnError>;
+}
+
+/// In-memory store for tests and single-node deployments
+#[derive(Default)]
+pub struct MemoryCredentialStore {
+    credentials: dashmap::DashMap<String, WebAuthnCredential>,
+}
+
+impl MemoryCredentialStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CredentialStore for MemoryCredentialStore {
+    fn load(&self) -> Result<Vec<WebAuthnCredential>, WebAuthnError> {
+        Ok(self.credentials.iter().map(|c| c.clone()).collect())
+    }
+
+    fn save(&self, credential: &WebAuthnCredential) -> Result<(), WebAuthnError> {
+        self.credentials.insert(credential.id_b64(), credential.clone());
+        Ok(())
+    }
+
+    fn remove(&self, credential_id: &str) -> Result<(), WebAuthnError> {
+        self.credentials.remove(credential_id);
+        Ok(())
+    }
+}
+
+/// One JSON file per credential as `<root>/<credential-id>.json`
+pub struct FileCredentialStore {
+    root: PathBuf,
+}
+
+impl FileCredentialStore {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, WebAuthnError> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).map_err(|e| storage(&root, e))?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, credential_id: &str) -> Result<PathBuf, WebAuthnError> {
+        // base64url IDs are filename-safe; anything else could escape root
+        if credential_id.is_empty()
+            || !credential_id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        {
+            return Err(WebAuthnError::Storage(format!("Invalid credential ID '{}'", credential_id)));
+        }
+        Ok(self.root.join(format!("{}.json", credential_id)))
+    }
+}
+
+impl CredentialStore for FileCredentialStore {
+    fn load(&self) -> Result<Vec<WebAuthnCredential>, WebAuthnError> {
+        let entries = std::fs::read_dir(&self.root).map_err(|e| storage(&self.root, e))?;
+
+        let mut credentials = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| storage(&self.root, e))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let content = std::fs::read(&path).map_err(|e| storage(&path, e))?;
+            match serde_json::from_slice(&content) {
+                Ok(credential) => credentials.push(credential),
+                Err(e) => tracing::warn!("Skipping unreadable WebAuthn credential {}: {}", path.display(), e),
+            }
+        }
+        Ok(credentials)
+    }
+
+    fn save(&self, credential: &WebAuthnCredential) -> Result<(), WebAuthnError> {
+        let path = self.path(&credential.id_b64())?;
+        let content = serde_json::to_vec_pretty(credential)
+            .map_err(|e| WebAuthnError::Storage(e.to_string()))?;
+
+        // Write aside and rename so a counter update is never half-written
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        let write = std::fs::write(&partial, content).and_then(|_| std::fs::rename(&partial, &path));
+        if let Err(e) = write {
+            let _ = std::fs::remove_file(&partial);
+            return Err(storage(&path, e));
+        }
+        Ok(())
+    }
+
+    fn remove(&self, credential_id: &str) -> Result<(), WebAuthnError> {
+        let path = self.path(credential_id)?;
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(storage(&path, e)),
+        }
+    }
+}
+
+fn storage(path: &Path, e: std::io::Error) -> WebAuthnError {
+    WebAuthnError::Storage(format!("{}: {}", path.display(), e))
+}
@@ -218,7 +218,7 @@ impl MicroSegmentationEngine {
         false
     }
     
-    fn get_segment_for_ip(&self, ip: &IpAddr) -> Option<dashmap::mapref::one::Ref<String, NetworkSegment>> {
+    fn get_segment_for_ip(&self, ip: &IpAddr) -> Option<dashmap::mapref::multiple::RefMulti<'_, String, NetworkSegment>> {
         for segment in self.segments.iter() {
             if let Ok(network) = segment.cidr.parse::<ipnetwork::IpNetwork>() {
                 if network.contains(*ip) {
@@ -229,7 +229,7 @@ impl MicroSegmentationEngine {
         None
     }
     
-    fn get_segment_for_resource(&self, resource: &Resource) -> Option<dashmap::mapref::one::Ref<String, NetworkSegment>> {
+    fn get_segment_for_resource(&self, resource: &Resource) -> Option<dashmap::mapref::multiple::RefMulti<'_, String, NetworkSegment>> {
         for segment in self.segments.iter() {
             if segment.resources.contains(&resource.id) {
                 return Some(segment);
@@ -45,7 +45,7 @@ pub struct ConnectionInfo {
     pub client_config: String,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TunnelState {
     Pending,
     Active,
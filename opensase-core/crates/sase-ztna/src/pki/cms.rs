@@ -0,0 +1,292 @@
+//! CMS (PKCS#7) messages
+//!
+//! Degenerate certs-only SignedData for EST and SCEP certificate
+//! distribution, and the SignedData/EnvelopedData pair SCEP wraps its
+//! requests and responses in. Content encryption is AES-CBC with RSA key
+//! transport, which is what SCEP clients use in practice.
+
+use super::der::{self, oid, Tlv};
+use super::x509::Certificate;
+use super::PkiError;
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use rand::RngCore;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// ContentInfo wrapping `content` of the given type
+fn content_info(content_type: &str, content: &[u8]) -> Vec<u8> {
+    der::sequence(&[&der::oid(content_type), &der::explicit(0, content)])
+}
+
+/// Unwrap a ContentInfo, checking its type
+fn open_content_info<'a>(data: &'a [u8], expected: &str) -> Result<Tlv<'a>, PkiError> {
+    let parts = der::parse(data)?.expect(der::SEQUENCE)?.children()?;
+    let [content_type, content] = parts.as_slice() else {
+        return Err(PkiError::Malformed("bad ContentInfo".to_string()));
+    };
+    let content_type = content_type.oid()?;
+    if content_type != expected {
+        return Err(PkiError::Malformed(format!("expected content type {}, found {}", expected, content_type)));
+    }
+    content.expect(0xA0)?.inner()
+}
+
+/// certs-only SignedData (RFC 5652 5.1, "degenerate" case)
+pub(crate) fn certs_only(certificates: &[&[u8]]) -> Vec<u8> {
+    let signed_data = der::sequence(&[
+        &der::integer(1),
+        &der::set(&[]),
+        &der::sequence(&[&der::oid(oid::PKCS7_DATA)]),
+        &der::explicit(0, &certificates.concat()),
+        &der::set(&[]),
+    ]);
+    content_info(oid::PKCS7_SIGNED_DATA, &signed_data)
+}
+
+// =============================================================================
+// SignedData
+// =============================================================================
+
+/// A SignedData whose signer signature has been verified
+pub(crate) struct SignedMessage {
+    pub content: Vec<u8>,
+    /// Certificate of the signer
+    pub signer: Vec<u8>,
+    /// Signed attributes as (type, first value)
+    pub attributes: Vec<(String, Vec<u8>)>,
+}
+
+impl SignedMessage {
+    /// Value of a signed attribute: the contents of its first value
+    pub fn attribute(&self, id: &str) -> Option<&[u8]> {
+        self.attributes.iter().find(|(a, _)| a == id).map(|(_, v)| v.as_slice())
+    }
+
+    /// Open a SignedData with one signer that carries its own certificate
+    pub fn open(data: &[u8]) -> Result<Self, PkiError> {
+        let signed_data = open_content_info(data, oid::PKCS7_SIGNED_DATA)?;
+        let fields = signed_data.expect(der::SEQUENCE)?.children()?;
+        if fields.len() < 4 {
+            return Err(PkiError::Malformed("bad SignedData".to_string()));
+        }
+
+        let encapsulated = fields[2].expect(der::SEQUENCE)?.children()?;
+        let content = match encapsulated.get(1) {
+            Some(explicit) => explicit.expect(0xA0)?.inner()?.octets()?,
+            None => Vec::new(),
+        };
+        let certificates = fields.iter()
+            .find(|f| f.tag == 0xA0)
+            .map(|f| f.children())
+            .transpose()?
+            .unwrap_or_default();
+        let signer_infos = fields.last().expect("length checked").expect(der::SET)?.children()?;
+        let signer_info = signer_infos.first()
+            .ok_or_else(|| PkiError::Malformed("SignedData has no signer".to_string()))?;
+
+        // SignerInfo: version, sid, digestAlgorithm, [0] signedAttrs,
+        // signatureAlgorithm, signature
+        let info = signer_info.expect(der::SEQUENCE)?.children()?;
+        if info.len() < 6 || info[3].tag != 0xA0 {
+            return Err(PkiError::Malformed("SignerInfo without signed attributes".to_string()));
+        }
+        let digest_algorithm = info[2].children()?.first()
+            .ok_or_else(|| PkiError::Malformed("empty digest algorithm".to_string()))?
+            .oid()?;
+        let signature_algorithm = info[4].children()?.first()
+            .ok_or_else(|| PkiError::Malformed("empty signature algorithm".to_string()))?
+            .oid()?;
+        let signature = info[5].expect(der::OCTET_STRING)?.value;
+
+        // The signer is found by issuer and serial; SCEP requesters send
+        // exactly one certificate so fall back to it
+        let sid = info[1];
+        let signer = certificates.iter()
+            .find(|c| Certificate::parse(c.raw).map(|c| c.issuer_and_serial() == sid.raw).unwrap_or(false))
+            .or(certificates.first())
+            .ok_or_else(|| PkiError::Malformed("SignedData carries no signer certificate".to_string()))?;
+        let signer_cert = Certificate::parse(signer.raw)?;
+
+        // Signed attributes are signed as an explicit SET OF
+        let mut signed = info[3].raw.to_vec();
+        signed[0] = der::SET;
+        let signature_algorithm = match (signature_algorithm.as_str(), digest_algorithm.as_str()) {
+            (oid::RSA_ENCRYPTION, oid::SHA256) => oid::RSA_SHA256,
+            (oid::RSA_ENCRYPTION, oid::SHA384) => oid::RSA_SHA384,
+            (oid::RSA_ENCRYPTION, oid::SHA512) => oid::RSA_SHA512,
+            (other, _) => other,
+        };
+        signer_cert.public_key.verify(signature_algorithm, &signed, signature)?;
+
+        let mut attributes = Vec::new();
+        for attribute in info[3].children()? {
+            let parts = attribute.expect(der::SEQUENCE)?.children()?;
+            if parts.len() != 2 {
+                continue;
+            }
+            if let Some(value) = parts[1].children()?.first() {
+                attributes.push((parts[0].oid()?, value.octets()?));
+            }
+        }
+
+        let digest = match digest_algorithm.as_str() {
+            oid::SHA256 => Sha256::digest(&content).to_vec(),
+            oid::SHA384 => Sha384::digest(&content).to_vec(),
+            oid::SHA512 => Sha512::digest(&content).to_vec(),
+            other => return Err(PkiError::UnsupportedKey(format!("digest algorithm {}", other))),
+        };
+        let message = Self { content, signer: signer.raw.to_vec(), attributes };
+        if message.attribute(oid::MESSAGE_DIGEST) != Some(digest.as_slice()) {
+            return Err(PkiError::BadSignature);
+        }
+        Ok(message)
+    }
+}
+
+/// SignedData over `content` (empty for none) signed by an RSA key
+/// (sha256WithRSAEncryption). `attributes` are complete Attribute encodings
+/// added to contentType and messageDigest.
+pub(crate) fn sign_rsa(
+    content: Option<&[u8]>,
+    attributes: Vec<Vec<u8>>,
+    signer_certificate: &[u8],
+    signer_key: &ring::signature::RsaKeyPair,
+    signature_len: usize,
+) -> Result<Vec<u8>, PkiError> {
+    let signer = Certificate::parse(signer_certificate)?;
+    let digest = Sha256::digest(content.unwrap_or_default());
+
+    let mut all = vec![
+        attribute(oid::CONTENT_TYPE, &der::oid(oid::PKCS7_DATA)),
+        attribute(oid::MESSAGE_DIGEST, &der::octet_string(&digest)),
+    ];
+    all.extend(attributes);
+    let refs: Vec<&[u8]> = all.iter().map(Vec::as_slice).collect();
+    let signed = der::set(&refs);
+
+    let mut signature = vec![0u8; signature_len];
+    signer_key.sign(&ring::signature::RSA_PKCS1_SHA256, &ring::rand::SystemRandom::new(), &signed, &mut signature)
+        .map_err(|_| PkiError::Internal("signing failed".to_string()))?;
+
+    // Implicit [0] replaces the SET tag
+    let mut signed_attributes = signed;
+    signed_attributes[0] = 0xA0;
+    let signer_info = der::sequence(&[
+        &der::integer(1),
+        &signer.issuer_and_serial(),
+        &der::algorithm(oid::SHA256),
+        &signed_attributes,
+        &der::algorithm(oid::RSA_SHA256),
+        &der::octet_string(&signature),
+    ]);
+
+    let encapsulated = match content {
+        Some(content) => der::sequence(&[&der::oid(oid::PKCS7_DATA), &der::explicit(0, &der::octet_string(content))]),
+        None => der::sequence(&[&der::oid(oid::PKCS7_DATA)]),
+    };
+    let signed_data = der::sequence(&[
+        &der::integer(1),
+        &der::set(&[&der::algorithm(oid::SHA256)]),
+        &encapsulated,
+        &der::explicit(0, signer_certificate),
+        &der::set(&[&signer_info]),
+    ]);
+    Ok(content_info(oid::PKCS7_SIGNED_DATA, &signed_data))
+}
+
+/// Attribute with one value
+pub(crate) fn attribute(id: &str, value: &[u8]) -> Vec<u8> {
+    der::sequence(&[&der::oid(id), &der::set(&[value])])
+}
+
+// =============================================================================
+// EnvelopedData
+// =============================================================================
+
+/// Decrypt an EnvelopedData addressed to `key` through RSA key transport
+pub(crate) fn decrypt(data: &[u8], key: &RsaPrivateKey) -> Result<Vec<u8>, PkiError> {
+    let enveloped = open_content_info(data, oid::PKCS7_ENVELOPED_DATA)?;
+    let mut fields = enveloped.expect(der::SEQUENCE)?.children()?;
+    if fields.get(1).map(|f| f.tag) == Some(0xA0) {
+        // originatorInfo
+        fields.remove(1);
+    }
+    if fields.len() < 3 {
+        return Err(PkiError::Malformed("bad EnvelopedData".to_string()));
+    }
+
+    // KeyTransRecipientInfo: version, rid, keyEncryptionAlgorithm, encryptedKey
+    let recipients = fields[1].expect(der::SET)?.children()?;
+    let encrypted_key = recipients.iter()
+        .filter(|r| r.tag == der::SEQUENCE)
+        .filter_map(|r| r.children().ok())
+        .find(|parts| parts.len() == 4)
+        .map(|parts| parts[3].value.to_vec())
+        .ok_or_else(|| PkiError::Malformed("no key transport recipient".to_string()))?;
+    let content_key = key.decrypt(Pkcs1v15Encrypt, &encrypted_key)
+        .map_err(|_| PkiError::Malformed("content key decryption failed".to_string()))?;
+
+    let encrypted = fields[2].expect(der::SEQUENCE)?.children()?;
+    if encrypted.len() < 3 {
+        return Err(PkiError::Malformed("EnvelopedData has no content".to_string()));
+    }
+    let algorithm = encrypted[1].children()?;
+    let cipher = algorithm.first()
+        .ok_or_else(|| PkiError::Malformed("empty content encryption algorithm".to_string()))?
+        .oid()?;
+    let iv = algorithm.get(1)
+        .ok_or_else(|| PkiError::Malformed("missing IV".to_string()))?
+        .value;
+    let ciphertext = encrypted[2].octets()?;
+
+    let bad = |_| PkiError::Malformed("content decryption failed".to_string());
+    match cipher.as_str() {
+        oid::AES128_CBC => cbc::Decryptor::<aes::Aes128>::new_from_slices(&content_key, iv)
+            .map_err(|_| PkiError::Malformed("bad content key".to_string()))?
+            .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext).map_err(bad),
+        oid::AES192_CBC => cbc::Decryptor::<aes::Aes192>::new_from_slices(&content_key, iv)
+            .map_err(|_| PkiError::Malformed("bad content key".to_string()))?
+            .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext).map_err(bad),
+        oid::AES256_CBC => cbc::Decryptor::<aes::Aes256>::new_from_slices(&content_key, iv)
+            .map_err(|_| PkiError::Malformed("bad content key".to_string()))?
+            .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext).map_err(bad),
+        other => Err(PkiError::UnsupportedKey(format!("content encryption {}", other))),
+    }
+}
+
+/// Encrypt `content` to the RSA key in `recipient`'s certificate (AES-256-CBC)
+pub(crate) fn encrypt(content: &[u8], recipient: &[u8]) -> Result<Vec<u8>, PkiError> {
+    let certificate = Certificate::parse(recipient)?;
+    let public = RsaPublicKey::from_public_key_der(&certificate.public_key.spki)
+        .map_err(|_| PkiError::UnsupportedKey("SCEP responses need an RSA recipient".to_string()))?;
+
+    let mut rng = rand::thread_rng();
+    let mut content_key = [0u8; 32];
+    let mut iv = [0u8; 16];
+    rng.fill_bytes(&mut content_key);
+    rng.fill_bytes(&mut iv);
+
+    let ciphertext = cbc::Encryptor::<aes::Aes256>::new(&content_key.into(), &iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(content);
+    let encrypted_key = public.encrypt(&mut rng, Pkcs1v15Encrypt, &content_key)
+        .map_err(|e| PkiError::Internal(format!("key transport failed: {}", e)))?;
+
+    let recipient_info = der::sequence(&[
+        &der::integer(0),
+        &certificate.issuer_and_serial(),
+        &der::algorithm(oid::RSA_ENCRYPTION),
+        &der::octet_string(&encrypted_key),
+    ]);
+    let encrypted_content = der::sequence(&[
+        &der::oid(oid::PKCS7_DATA),
+        &der::sequence(&[&der::oid(oid::AES256_CBC), &der::octet_string(&iv)]),
+        &der::implicit(0, &ciphertext),
+    ]);
+    let enveloped = der::sequence(&[
+        &der::integer(0),
+        &der::set(&[&recipient_info]),
+        &encrypted_content,
+    ]);
+    Ok(content_info(oid::PKCS7_ENVELOPED_DATA, &enveloped))
+}
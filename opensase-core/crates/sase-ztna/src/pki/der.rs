@@ -0,0 +1,370 @@
+//! DER reading and writing
+//!
+//! Just enough ASN.1 for certificates, CSRs, CRLs and the CMS messages
+//! SCEP wraps them in. The reader also accepts the BER indefinite lengths
+//! and constructed OCTET STRINGs some SCEP clients send.
+
+use super::PkiError;
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone, Utc};
+
+pub(crate) const BOOLEAN: u8 = 0x01;
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const BIT_STRING: u8 = 0x03;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const NULL: u8 = 0x05;
+pub(crate) const OID: u8 = 0x06;
+pub(crate) const ENUMERATED: u8 = 0x0A;
+pub(crate) const UTF8_STRING: u8 = 0x0C;
+pub(crate) const PRINTABLE_STRING: u8 = 0x13;
+pub(crate) const UTC_TIME: u8 = 0x17;
+pub(crate) const GENERALIZED_TIME: u8 = 0x18;
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+
+/// Object identifiers used by the CA
+pub(crate) mod oid {
+    pub const COMMON_NAME: &str = "2.5.4.3";
+    pub const ORGANIZATION: &str = "2.5.4.10";
+
+    pub const EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+    pub const P256: &str = "1.2.840.10045.3.1.7";
+    pub const P384: &str = "1.3.132.0.34";
+    pub const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+    pub const ED25519: &str = "1.3.101.112";
+
+    pub const ECDSA_SHA256: &str = "1.2.840.10045.4.3.2";
+    pub const ECDSA_SHA384: &str = "1.2.840.10045.4.3.3";
+    pub const RSA_SHA256: &str = "1.2.840.113549.1.1.11";
+    pub const RSA_SHA384: &str = "1.2.840.113549.1.1.12";
+    pub const RSA_SHA512: &str = "1.2.840.113549.1.1.13";
+    pub const SHA256: &str = "2.16.840.1.101.3.4.2.1";
+    pub const SHA384: &str = "2.16.840.1.101.3.4.2.2";
+    pub const SHA512: &str = "2.16.840.1.101.3.4.2.3";
+
+    pub const AES128_CBC: &str = "2.16.840.1.101.3.4.1.2";
+    pub const AES192_CBC: &str = "2.16.840.1.101.3.4.1.22";
+    pub const AES256_CBC: &str = "2.16.840.1.101.3.4.1.42";
+
+    pub const SUBJECT_KEY_ID: &str = "2.5.29.14";
+    pub const KEY_USAGE: &str = "2.5.29.15";
+    pub const SUBJECT_ALT_NAME: &str = "2.5.29.17";
+    pub const BASIC_CONSTRAINTS: &str = "2.5.29.19";
+    pub const CRL_NUMBER: &str = "2.5.29.20";
+    pub const CRL_REASON: &str = "2.5.29.21";
+    pub const CRL_DISTRIBUTION_POINTS: &str = "2.5.29.31";
+    pub const AUTHORITY_KEY_ID: &str = "2.5.29.35";
+    pub const EXT_KEY_USAGE: &str = "2.5.29.37";
+    pub const CLIENT_AUTH: &str = "1.3.6.1.5.5.7.3.2";
+
+    pub const PKCS7_DATA: &str = "1.2.840.113549.1.7.1";
+    pub const PKCS7_SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+    pub const PKCS7_ENVELOPED_DATA: &str = "1.2.840.113549.1.7.3";
+    pub const CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+    pub const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+    pub const CHALLENGE_PASSWORD: &str = "1.2.840.113549.1.9.7";
+
+    pub const SCEP_MESSAGE_TYPE: &str = "2.16.840.1.113733.1.9.2";
+    pub const SCEP_PKI_STATUS: &str = "2.16.840.1.113733.1.9.3";
+    pub const SCEP_FAIL_INFO: &str = "2.16.840.1.113733.1.9.4";
+    pub const SCEP_SENDER_NONCE: &str = "2.16.840.1.113733.1.9.5";
+    pub const SCEP_RECIPIENT_NONCE: &str = "2.16.840.1.113733.1.9.6";
+    pub const SCEP_TRANSACTION_ID: &str = "2.16.840.1.113733.1.9.7";
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// One element: its tag, contents and the full encoding
+#[derive(Clone, Copy)]
+pub(crate) struct Tlv<'a> {
+    pub tag: u8,
+    pub value: &'a [u8],
+    pub raw: &'a [u8],
+}
+
+fn malformed(what: &str) -> PkiError {
+    PkiError::Malformed(what.to_string())
+}
+
+/// Read the element at the start of `data`, returning it and what follows
+pub(crate) fn read(data: &[u8]) -> Result<(Tlv<'_>, &[u8]), PkiError> {
+    if data.len() < 2 {
+        return Err(malformed("truncated element"));
+    }
+    let tag = data[0];
+    if tag & 0x1F == 0x1F {
+        return Err(malformed("high tag numbers are not supported"));
+    }
+
+    let first = data[1];
+    if first == 0x80 {
+        // BER indefinite length: children up to an end-of-contents marker
+        if tag & 0x20 == 0 {
+            return Err(malformed("indefinite length on a primitive element"));
+        }
+        let mut rest = &data[2..];
+        loop {
+            if rest.len() >= 2 && rest[0] == 0 && rest[1] == 0 {
+                let end = data.len() - rest.len();
+                let tlv = Tlv { tag, value: &data[2..end], raw: &data[..end + 2] };
+                return Ok((tlv, &rest[2..]));
+            }
+            rest = read(rest)?.1;
+        }
+    }
+
+    let (length, header) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count > 4 || data.len() < 2 + count {
+            return Err(malformed("bad length"));
+        }
+        let length = data[2..2 + count].iter().fold(0usize, |n, b| (n << 8) | *b as usize);
+        (length, 2 + count)
+    };
+    let end = header.checked_add(length)
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| malformed("element runs past the end of its container"))?;
+    Ok((Tlv { tag, value: &data[header..end], raw: &data[..end] }, &data[end..]))
+}
+
+/// Read a complete element, rejecting anything after it
+pub(crate) fn parse(data: &[u8]) -> Result<Tlv<'_>, PkiError> {
+    let (tlv, rest) = read(data)?;
+    if !rest.is_empty() {
+        return Err(malformed("trailing data"));
+    }
+    Ok(tlv)
+}
+
+impl<'a> Tlv<'a> {
+    pub fn expect(self, tag: u8) -> Result<Self, PkiError> {
+        if self.tag != tag {
+            return Err(PkiError::Malformed(format!("expected tag {:#04x}, found {:#04x}", tag, self.tag)));
+        }
+        Ok(self)
+    }
+
+    /// Child elements of a constructed element
+    pub fn children(&self) -> Result<Vec<Tlv<'a>>, PkiError> {
+        let mut children = Vec::new();
+        let mut rest = self.value;
+        while !rest.is_empty() {
+            let (child, next) = read(rest)?;
+            children.push(child);
+            rest = next;
+        }
+        Ok(children)
+    }
+
+    /// The single element inside an explicitly tagged one
+    pub fn inner(&self) -> Result<Tlv<'a>, PkiError> {
+        parse(self.value)
+    }
+
+    pub fn oid(&self) -> Result<String, PkiError> {
+        self.expect(OID)?;
+        decode_oid(self.value)
+    }
+
+    /// OCTET STRING contents, joining the pieces of a constructed one
+    pub fn octets(&self) -> Result<Vec<u8>, PkiError> {
+        if self.tag & 0x20 == 0 {
+            return Ok(self.value.to_vec());
+        }
+        let mut joined = Vec::new();
+        for piece in self.children()? {
+            joined.extend(piece.octets()?);
+        }
+        Ok(joined)
+    }
+
+    /// BIT STRING contents without the unused-bits octet
+    pub fn bits(&self) -> Result<&'a [u8], PkiError> {
+        self.expect(BIT_STRING)?;
+        match self.value.split_first() {
+            Some((0, bits)) => Ok(bits),
+            _ => Err(malformed("BIT STRING with unused bits")),
+        }
+    }
+
+    pub fn string(&self) -> Result<String, PkiError> {
+        String::from_utf8(self.value.to_vec()).map_err(|_| malformed("invalid string"))
+    }
+
+    pub fn time(&self) -> Result<DateTime<Utc>, PkiError> {
+        let text = std::str::from_utf8(self.value).map_err(|_| malformed("invalid time"))?;
+        let full = match self.tag {
+            // RFC 5280: two-digit years 50-99 are 19xx
+            UTC_TIME if text.len() >= 2 => {
+                let century = if &text[..2] < "50" { "20" } else { "19" };
+                format!("{}{}", century, text)
+            }
+            GENERALIZED_TIME => text.to_string(),
+            _ => return Err(malformed("expected a time")),
+        };
+        NaiveDateTime::parse_from_str(&full, "%Y%m%d%H%M%SZ")
+            .map(|time| Utc.from_utc_datetime(&time))
+            .map_err(|_| PkiError::Malformed(format!("unsupported time {}", text)))
+    }
+}
+
+fn decode_oid(bytes: &[u8]) -> Result<String, PkiError> {
+    let mut arcs = Vec::new();
+    let mut value = 0u64;
+    for (i, b) in bytes.iter().enumerate() {
+        if value > u64::MAX >> 7 {
+            return Err(malformed("OID arc too large"));
+        }
+        value = (value << 7) | (b & 0x7F) as u64;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        } else if i == bytes.len() - 1 {
+            return Err(malformed("truncated OID"));
+        }
+    }
+    if arcs.is_empty() {
+        return Err(malformed("empty OID"));
+    }
+    Ok(arcs.iter().map(u64::to_string).collect::<Vec<_>>().join("."))
+}
+
+// =============================================================================
+// Writing
+// =============================================================================
+
+pub(crate) fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(value.len() + 6);
+    out.push(tag);
+    let len = value.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = (len as u32).to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (4 - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+pub(crate) fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    tlv(SEQUENCE, &parts.concat())
+}
+
+/// SET OF, with the members in DER order
+pub(crate) fn set(parts: &[&[u8]]) -> Vec<u8> {
+    let mut sorted = parts.to_vec();
+    sorted.sort();
+    tlv(SET, &sorted.concat())
+}
+
+/// Context-specific tag `[n]`, constructed (explicit or implicit SEQUENCE)
+pub(crate) fn explicit(n: u8, value: &[u8]) -> Vec<u8> {
+    tlv(0xA0 | n, value)
+}
+
+/// Context-specific tag `[n]`, primitive
+pub(crate) fn implicit(n: u8, value: &[u8]) -> Vec<u8> {
+    tlv(0x80 | n, value)
+}
+
+pub(crate) fn oid(dotted: &str) -> Vec<u8> {
+    let arcs: Vec<u64> = dotted.split('.').map(|arc| arc.parse().expect("valid OID")).collect();
+    let mut body = Vec::new();
+    let mut push_arc = |mut value: u64| {
+        let mut chunk = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            chunk.push(0x80 | (value & 0x7F) as u8);
+            value >>= 7;
+        }
+        body.extend(chunk.iter().rev());
+    };
+    push_arc(arcs[0] * 40 + arcs[1]);
+    for arc in &arcs[2..] {
+        push_arc(*arc);
+    }
+    tlv(OID, &body)
+}
+
+/// INTEGER from unsigned big-endian bytes
+pub(crate) fn unsigned(bytes: &[u8]) -> Vec<u8> {
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(bytes.len().saturating_sub(1));
+    let bytes = &bytes[skip..];
+    if bytes.is_empty() {
+        return tlv(INTEGER, &[0]);
+    }
+    if bytes[0] & 0x80 != 0 {
+        return tlv(INTEGER, &[&[0u8][..], bytes].concat());
+    }
+    tlv(INTEGER, bytes)
+}
+
+pub(crate) fn integer(n: u64) -> Vec<u8> {
+    unsigned(&n.to_be_bytes())
+}
+
+pub(crate) fn boolean(value: bool) -> Vec<u8> {
+    tlv(BOOLEAN, &[if value { 0xFF } else { 0 }])
+}
+
+pub(crate) fn null() -> Vec<u8> {
+    tlv(NULL, &[])
+}
+
+pub(crate) fn octet_string(bytes: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, bytes)
+}
+
+/// BIT STRING with no unused bits
+pub(crate) fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    tlv(BIT_STRING, &[&[0u8][..], bytes].concat())
+}
+
+/// Named-bit BIT STRING (key usage) from bit numbers
+pub(crate) fn named_bits(bits: &[u8]) -> Vec<u8> {
+    let highest = bits.iter().copied().max().unwrap_or(0) as usize;
+    let mut bytes = vec![0u8; highest / 8 + 1];
+    for bit in bits {
+        bytes[*bit as usize / 8] |= 0x80 >> (bit % 8);
+    }
+    let unused = 7 - (highest % 8) as u8;
+    tlv(BIT_STRING, &[&[unused][..], &bytes].concat())
+}
+
+pub(crate) fn utf8(text: &str) -> Vec<u8> {
+    tlv(UTF8_STRING, text.as_bytes())
+}
+
+pub(crate) fn printable(text: &str) -> Vec<u8> {
+    tlv(PRINTABLE_STRING, text.as_bytes())
+}
+
+/// UTCTime through 2049, GeneralizedTime after (RFC 5280)
+pub(crate) fn time(at: DateTime<Utc>) -> Vec<u8> {
+    if at.year() < 2050 {
+        tlv(UTC_TIME, at.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+    } else {
+        tlv(GENERALIZED_TIME, at.format("%Y%m%d%H%M%SZ").to_string().as_bytes())
+    }
+}
+
+/// AlgorithmIdentifier; RSA algorithms carry an explicit NULL
+pub(crate) fn algorithm(algorithm: &str) -> Vec<u8> {
+    if algorithm.starts_with("1.2.840.113549.") || algorithm.starts_with("2.16.840.1.101.3.4.2.") {
+        sequence(&[&oid(algorithm), &null()])
+    } else {
+        sequence(&[&oid(algorithm)])
+    }
+}
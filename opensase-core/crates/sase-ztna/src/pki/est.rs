@@ -0,0 +1,149 @@
+//! EST enrollment (RFC 7030)
+//!
+//! Transport-agnostic: the HTTPS front end passes in the method, path,
+//! Authorization header, the client certificate from the TLS handshake and
+//! the body, and writes back the response. Bodies are base64 DER as the
+//! RFC requires.
+//!
+//! - `GET  /.well-known/est/cacerts`: the CA certificate
+//! - `POST /.well-known/est/simpleenroll`: first certificate, with HTTP
+//!   Basic auth of device id and enrollment token
+//! - `POST /.well-known/est/simplereenroll`: renewal, authenticated by the
+//!   current certificate on the TLS connection
+//! - `GET  /.well-known/est/csrattrs`: no attributes required
+//!
+//! Server-side key generation and full CMC are not offered; devices
+//! always generate their own keys.
+
+use super::{cms, DeviceCa, EnrollmentAuth, EnrollmentProtocol, PkiError};
+use base64::Engine;
+use std::sync::Arc;
+
+pub struct EstRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub authorization: Option<&'a str>,
+    /// Certificate the client presented during the TLS handshake (DER)
+    pub client_certificate: Option<&'a [u8]>,
+    pub body: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct EstResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl EstResponse {
+    fn pkcs7(der: &[u8]) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/pkcs7-mime; smime-type=certs-only",
+            headers: vec![("Content-Transfer-Encoding", "base64".to_string())],
+            body: base64::engine::general_purpose::STANDARD.encode(der).into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            headers: Vec::new(),
+            body: message.as_bytes().to_vec(),
+        }
+    }
+
+    fn unauthorized() -> Self {
+        let mut response = Self::error(401, "Authentication required");
+        response.headers.push(("WWW-Authenticate", "Basic realm=\"est\"".to_string()));
+        response
+    }
+}
+
+pub struct EstServer {
+    ca: Arc<DeviceCa>,
+}
+
+impl EstServer {
+    pub fn new(ca: Arc<DeviceCa>) -> Self {
+        Self { ca }
+    }
+
+    pub async fn handle(&self, request: &EstRequest<'_>) -> EstResponse {
+        // Optional CA label segment: /.well-known/est/<label>/<operation>
+        let Some(rest) = request.path.strip_prefix("/.well-known/est/") else {
+            return EstResponse::error(404, "Not found");
+        };
+        let operation = rest.rsplit('/').next().unwrap_or(rest);
+
+        match (request.method, operation) {
+            ("GET", "cacerts") => EstResponse::pkcs7(&cms::certs_only(&[self.ca.certificate()])),
+            ("GET", "csrattrs") => EstResponse::error(204, ""),
+            ("POST", "simpleenroll") => {
+                let credentials = request.authorization.and_then(basic_credentials);
+                let auth = match (&credentials, request.client_certificate) {
+                    (Some((device_id, token)), _) => EnrollmentAuth::Token {
+                        device_id: Some(device_id.as_str()),
+                        token: token.as_str(),
+                    },
+                    // A device re-enrolling from scratch while it still
+                    // holds a valid certificate
+                    (None, Some(certificate)) => EnrollmentAuth::Certificate(certificate),
+                    (None, None) => return EstResponse::unauthorized(),
+                };
+                self.enroll(auth, request.body).await
+            }
+            ("POST", "simplereenroll") => match request.client_certificate {
+                Some(certificate) => self.enroll(EnrollmentAuth::Certificate(certificate), request.body).await,
+                None => EstResponse::unauthorized(),
+            },
+            (_, "cacerts" | "csrattrs" | "simpleenroll" | "simplereenroll") => {
+                EstResponse::error(405, "Method not allowed")
+            }
+            _ => EstResponse::error(404, "Not found"),
+        }
+    }
+
+    async fn enroll(&self, auth: EnrollmentAuth<'_>, body: &[u8]) -> EstResponse {
+        // Base64 PKCS#10, possibly line-wrapped
+        let encoded: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+        let csr = match base64::engine::general_purpose::STANDARD.decode(encoded) {
+            Ok(csr) => csr,
+            Err(_) => return EstResponse::error(400, "Request body is not base64"),
+        };
+
+        match self.ca.enroll(auth, &csr, EnrollmentProtocol::Est).await {
+            Ok(issued) => EstResponse::pkcs7(&cms::certs_only(&[issued.der.as_slice()])),
+            Err(e) => {
+                let status = match e {
+                    PkiError::InvalidCredentials
+                    | PkiError::UnknownIssuer
+                    | PkiError::UnknownCertificate(_)
+                    | PkiError::Expired
+                    | PkiError::Superseded => 401,
+                    PkiError::DeviceNotRegistered(_) | PkiError::PostureDenied(_) | PkiError::Revoked(_) => 403,
+                    PkiError::Malformed(_) | PkiError::UnsupportedKey(_) | PkiError::BadSignature => 400,
+                    PkiError::Internal(_) => 500,
+                };
+                if status == 401 {
+                    return EstResponse::unauthorized();
+                }
+                EstResponse::error(status, &e.to_string())
+            }
+        }
+    }
+}
+
+/// (user, password) from an HTTP Basic Authorization header
+fn basic_credentials(header: &str) -> Option<(String, String)> {
+    let (scheme, encoded) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
@@ -0,0 +1,1080 @@
+//! Device Certificate Authority
+//!
+//! Issues the client certificates devices present on mutual TLS, so a
+//! device proves who it is with a key it holds rather than with posture it
+//! reports. Devices enroll over EST (RFC 7030) or SCEP (RFC 8894) with a
+//! one-time enrollment token; the CA only signs for registered devices
+//! whose posture meets policy, and weaker devices get shorter lifetimes.
+//! Devices renew with their current certificate before it lapses.
+//!
+//! Certificates are revoked when the device is reported compromised (risk
+//! signals) or falls out of posture, and revocation takes effect at once
+//! for `verify_peer` and with the next CRL for everyone else. A verified
+//! certificate is the device identity factor in `trust_engine` scoring.
+
+mod cms;
+pub(crate) mod der;
+mod est;
+mod scep;
+pub(crate) mod x509;
+
+pub use est::{EstRequest, EstResponse, EstServer};
+pub use scep::{ScepRequest, ScepResponse, ScepServer};
+pub use x509::KeyAlgorithm;
+
+use crate::audit::{AuditEventType, AuditLogger};
+use crate::device::DeviceAssessor;
+use crate::{DeviceCertificate, RiskSeverity, RiskSignal, RiskSignalType, TrustLevel};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use der::oid;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio_rustls::rustls;
+use x509::{key_usage, CertificationRequest, Certificate, PublicKey, TbsCertificate};
+
+#[derive(Debug, Clone)]
+pub struct CaConfig {
+    pub common_name: String,
+    pub organization: String,
+    pub ca_validity: Duration,
+    /// Device certificate lifetime at high or full trust
+    pub device_validity: Duration,
+    /// Lifetime for devices that only just meet `minimum_trust`
+    pub reduced_validity: Duration,
+    /// Devices assessed below this are refused certificates
+    pub minimum_trust: TrustLevel,
+    pub require_compliant: bool,
+    /// Renewal is due once less than this share of the lifetime remains
+    pub renewal_threshold: f64,
+    /// How long a replaced certificate keeps working, so a device that
+    /// lost the renewal response can retry
+    pub rollover_grace: Duration,
+    pub enrollment_token_ttl: Duration,
+    pub min_rsa_bits: usize,
+    pub crl_validity: Duration,
+    /// Published in issued certificates when set
+    pub crl_url: Option<String>,
+}
+
+impl Default for CaConfig {
+    fn default() -> Self {
+        Self {
+            common_name: "OpenSASE Device CA".to_string(),
+            organization: "OpenSASE".to_string(),
+            ca_validity: Duration::days(3650),
+            device_validity: Duration::days(30),
+            reduced_validity: Duration::days(7),
+            minimum_trust: TrustLevel::Medium,
+            require_compliant: true,
+            renewal_threshold: 0.33,
+            rollover_grace: Duration::hours(24),
+            enrollment_token_ttl: Duration::hours(24),
+            min_rsa_bits: 2048,
+            crl_validity: Duration::hours(24),
+            crl_url: None,
+        }
+    }
+}
+
+// =============================================================================
+// Issued Certificates
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnrollmentProtocol {
+    Est,
+    Scep,
+    /// Issued directly through `DeviceCa::enroll`
+    Api,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    Superseded,
+    CessationOfOperation,
+    /// Device fell out of posture policy
+    PrivilegeWithdrawn,
+}
+
+impl RevocationReason {
+    /// CRLReason code (RFC 5280 5.3.1)
+    fn code(self) -> u8 {
+        match self {
+            Self::Unspecified => 0,
+            Self::KeyCompromise => 1,
+            Self::Superseded => 4,
+            Self::CessationOfOperation => 5,
+            Self::PrivilegeWithdrawn => 9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CertificateStatus {
+    Active,
+    /// Replaced by a renewal; still accepted for `rollover_grace`
+    Superseded { by: String, at: DateTime<Utc> },
+    Revoked { reason: RevocationReason, at: DateTime<Utc> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedCertificate {
+    /// Serial number in hex
+    pub serial: String,
+    pub device_id: String,
+    pub owner_id: Option<String>,
+    /// SHA-256 of the certificate
+    pub fingerprint: String,
+    #[serde(skip)]
+    pub der: Vec<u8>,
+    pub key_algorithm: KeyAlgorithm,
+    /// Device trust when the certificate was issued
+    pub trust_level: TrustLevel,
+    pub protocol: EnrollmentProtocol,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub status: CertificateStatus,
+    /// Serial of the certificate this one renewed
+    pub renews: Option<String>,
+}
+
+impl IssuedCertificate {
+    pub fn is_revoked(&self) -> bool {
+        matches!(self.status, CertificateStatus::Revoked { .. })
+    }
+
+    fn renewal_due(&self, threshold: f64, now: DateTime<Utc>) -> bool {
+        let lifetime = (self.not_after - self.not_before).num_seconds() as f64;
+        let remaining = (self.not_after - now).num_seconds() as f64;
+        remaining < lifetime * threshold
+    }
+}
+
+/// A device authenticated by its certificate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    pub device_id: String,
+    pub owner_id: Option<String>,
+    pub serial: String,
+    pub fingerprint: String,
+    /// Device trust when the certificate was issued
+    pub trust_level: TrustLevel,
+    pub not_after: DateTime<Utc>,
+    /// The device should renew now
+    pub renewal_due: bool,
+}
+
+/// Outcome of checking the certificate a device presented, for trust scoring
+#[derive(Debug, Clone, Default)]
+pub enum DeviceCertificateState {
+    Verified(DeviceIdentity),
+    #[default]
+    Missing,
+    Invalid(String),
+    Revoked(RevocationReason),
+}
+
+/// How an enrollment is authenticated
+#[derive(Debug, Clone, Copy)]
+pub enum EnrollmentAuth<'a> {
+    /// One-time enrollment token, with the device it must belong to when
+    /// the protocol names one
+    Token { device_id: Option<&'a str>, token: &'a str },
+    /// The device's current certificate (renewal)
+    Certificate(&'a [u8]),
+}
+
+#[derive(Debug, Clone)]
+struct EnrollmentToken {
+    device_id: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PkiStats {
+    pub active: usize,
+    pub superseded: usize,
+    pub revoked: usize,
+    pub expired: usize,
+    pub pending_tokens: usize,
+}
+
+// =============================================================================
+// Certificate Authority
+// =============================================================================
+
+pub struct DeviceCa {
+    config: CaConfig,
+    key: EcdsaKeyPair,
+    certificate: Vec<u8>,
+    /// Encoded Name, issuer of everything the CA signs
+    subject: Vec<u8>,
+    public_key: PublicKey,
+    key_id: Vec<u8>,
+    not_after: DateTime<Utc>,
+    devices: Arc<DeviceAssessor>,
+    audit: Arc<AuditLogger>,
+    /// By serial
+    certificates: dashmap::DashMap<String, IssuedCertificate>,
+    /// Serials by device
+    by_device: dashmap::DashMap<String, Vec<String>>,
+    /// By SHA-256 of the token
+    tokens: dashmap::DashMap<String, EnrollmentToken>,
+    crl_number: AtomicU64,
+    rng: SystemRandom,
+}
+
+impl DeviceCa {
+    /// New P-256 CA key as PKCS#8, for `DeviceCa::new`
+    pub fn generate_key() -> Result<Vec<u8>, PkiError> {
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+            .map(|pkcs8| pkcs8.as_ref().to_vec())
+            .map_err(|_| PkiError::Internal("key generation failed".to_string()))
+    }
+
+    /// Load the CA from its PKCS#8 key. Without a certificate a new
+    /// self-signed one is made; keep it with the key so devices enrolled
+    /// earlier stay valid.
+    pub fn new(
+        config: CaConfig,
+        key_pkcs8: &[u8],
+        certificate: Option<Vec<u8>>,
+        devices: Arc<DeviceAssessor>,
+        audit: Arc<AuditLogger>,
+    ) -> Result<Self, PkiError> {
+        let rng = SystemRandom::new();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, key_pkcs8, &rng)
+            .map_err(|_| PkiError::UnsupportedKey("CA key must be P-256 PKCS#8".to_string()))?;
+        let spki = der::sequence(&[
+            &der::sequence(&[&der::oid(oid::EC_PUBLIC_KEY), &der::oid(oid::P256)]),
+            &der::bit_string(key.public_key().as_ref()),
+        ]);
+        let public_key = PublicKey::parse(der::parse(&spki)?)?;
+        let key_id = public_key.key_id();
+
+        let certificate = match certificate {
+            Some(certificate) => certificate,
+            None => {
+                let subject = x509::name(&config.common_name, Some(&config.organization));
+                let now = Utc::now();
+                TbsCertificate {
+                    serial: &new_serial(&rng)?,
+                    issuer: &subject,
+                    subject: &subject,
+                    not_before: now - Duration::minutes(5),
+                    not_after: now + config.ca_validity,
+                    spki: &spki,
+                    extensions: vec![
+                        x509::extension(oid::BASIC_CONSTRAINTS, true, &der::sequence(&[&der::boolean(true)])),
+                        x509::extension(oid::KEY_USAGE, true, &der::named_bits(&[
+                            key_usage::DIGITAL_SIGNATURE,
+                            key_usage::KEY_CERT_SIGN,
+                            key_usage::CRL_SIGN,
+                        ])),
+                        x509::extension(oid::SUBJECT_KEY_ID, false, &der::octet_string(&key_id)),
+                    ],
+                }.sign(&key, &rng)?
+            }
+        };
+
+        let parsed = Certificate::parse(&certificate)?;
+        if parsed.public_key.key != public_key.key {
+            return Err(PkiError::Malformed("CA certificate does not match the CA key".to_string()));
+        }
+        let subject = parsed.subject.to_vec();
+        let not_after = parsed.not_after;
+
+        Ok(Self {
+            config,
+            key,
+            certificate,
+            subject,
+            public_key,
+            key_id,
+            not_after,
+            devices,
+            audit,
+            certificates: dashmap::DashMap::new(),
+            by_device: dashmap::DashMap::new(),
+            tokens: dashmap::DashMap::new(),
+            crl_number: AtomicU64::new(0),
+            rng,
+        })
+    }
+
+    /// CA certificate (DER), the trust anchor for device certificates
+    pub fn certificate(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    pub fn config(&self) -> &CaConfig {
+        &self.config
+    }
+
+    /// Verifier for a TLS server that asks devices for certificates.
+    /// Connections without one are still accepted so they can be scored as
+    /// `DeviceCertificateState::Missing`; pass the presented certificate
+    /// to `certificate_state` for revocation and registry checks.
+    pub fn client_verifier(&self) -> Result<Arc<dyn rustls::server::ClientCertVerifier>, PkiError> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(&rustls::Certificate(self.certificate.clone()))
+            .map_err(|e| PkiError::Internal(format!("CA certificate rejected by rustls: {:?}", e)))?;
+        Ok(rustls::server::AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed())
+    }
+
+    // =========================================================================
+    // Enrollment
+    // =========================================================================
+
+    /// One-time secret a registered device enrolls with: the EST password
+    /// (with the device id as user name) or the SCEP challenge password
+    pub fn create_enrollment_token(&self, device_id: &str) -> Result<String, PkiError> {
+        if !self.devices.is_registered(device_id) {
+            return Err(PkiError::DeviceNotRegistered(device_id.to_string()));
+        }
+        let mut secret = [0u8; 24];
+        self.rng.fill(&mut secret)
+            .map_err(|_| PkiError::Internal("random generation failed".to_string()))?;
+        let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret);
+
+        self.tokens.insert(token_hash(&token), EnrollmentToken {
+            device_id: device_id.to_string(),
+            expires_at: Utc::now() + self.config.enrollment_token_ttl,
+        });
+        Ok(token)
+    }
+
+    /// Issue a certificate for the key in a PKCS#10 request. The subject is
+    /// always the device id; names in the request are ignored. Renewing
+    /// with a certificate supersedes it.
+    pub async fn enroll(
+        &self,
+        auth: EnrollmentAuth<'_>,
+        csr: &[u8],
+        protocol: EnrollmentProtocol,
+    ) -> Result<IssuedCertificate, PkiError> {
+        let (device_id, token, renews) = match auth {
+            EnrollmentAuth::Token { device_id, token } => {
+                let hash = token_hash(token);
+                let entry = self.tokens.get(&hash)
+                    .map(|t| t.clone())
+                    .ok_or(PkiError::InvalidCredentials)?;
+                if entry.expires_at <= Utc::now() {
+                    self.tokens.remove(&hash);
+                    return Err(PkiError::InvalidCredentials);
+                }
+                if device_id.is_some_and(|id| id != entry.device_id) {
+                    return Err(PkiError::InvalidCredentials);
+                }
+                (entry.device_id, Some(hash), None)
+            }
+            EnrollmentAuth::Certificate(der) => {
+                let identity = self.verify_peer(der)?;
+                (identity.device_id, None, Some(identity.serial))
+            }
+        };
+
+        let request = CertificationRequest::parse(csr)?;
+        if let KeyAlgorithm::Rsa { bits } = request.public_key.algorithm {
+            if bits < self.config.min_rsa_bits {
+                return Err(PkiError::UnsupportedKey(format!("{}-bit RSA", bits)));
+            }
+        }
+
+        // Spend the token before the posture gate: a device turned away
+        // needs a fresh one once it has been remediated
+        if let Some(hash) = token {
+            if self.tokens.remove(&hash).is_none() {
+                return Err(PkiError::InvalidCredentials);
+            }
+        }
+
+        // Posture gate
+        let device = self.devices.get(&device_id)
+            .ok_or_else(|| PkiError::DeviceNotRegistered(device_id.clone()))?;
+        let assessment = self.devices.assess(&device);
+        if assessment.trust_level < self.config.minimum_trust
+            || (self.config.require_compliant && !assessment.compliant)
+        {
+            let mut details = HashMap::new();
+            details.insert("trust_level".to_string(), format!("{:?}", assessment.trust_level));
+            details.insert("issues".to_string(), assessment.issues.join("; "));
+            self.audit.log_device_certificate(AuditEventType::DeviceEnrollmentDenied, &device_id, &device_id, details).await;
+            return Err(PkiError::PostureDenied(assessment.issues));
+        }
+
+        let now = Utc::now();
+        let lifetime = if assessment.trust_level >= TrustLevel::High {
+            self.config.device_validity
+        } else {
+            self.config.reduced_validity
+        };
+        let not_before = now - Duration::minutes(5);
+        let not_after = (now + lifetime).min(self.not_after);
+        let serial = new_serial(&self.rng)?;
+
+        let mut usage = vec![key_usage::DIGITAL_SIGNATURE];
+        if matches!(request.public_key.algorithm, KeyAlgorithm::Rsa { .. }) {
+            usage.push(key_usage::KEY_ENCIPHERMENT);
+        }
+        let san = der::sequence(&[&der::implicit(6, format!("urn:opensase:device:{}", device_id).as_bytes())]);
+        let mut extensions = vec![
+            x509::extension(oid::BASIC_CONSTRAINTS, true, &der::sequence(&[])),
+            x509::extension(oid::KEY_USAGE, true, &der::named_bits(&usage)),
+            x509::extension(oid::EXT_KEY_USAGE, false, &der::sequence(&[&der::oid(oid::CLIENT_AUTH)])),
+            x509::extension(oid::SUBJECT_ALT_NAME, false, &san),
+            x509::extension(oid::SUBJECT_KEY_ID, false, &der::octet_string(&request.public_key.key_id())),
+            x509::extension(oid::AUTHORITY_KEY_ID, false, &x509::authority_key_id(&self.key_id)),
+        ];
+        if let Some(url) = &self.config.crl_url {
+            extensions.push(x509::extension(oid::CRL_DISTRIBUTION_POINTS, false, &x509::crl_distribution_point(url)));
+        }
+
+        let subject = x509::name(&device_id, Some(&self.config.organization));
+        let certificate = TbsCertificate {
+            serial: &serial,
+            issuer: &self.subject,
+            subject: &subject,
+            not_before,
+            not_after,
+            spki: &request.public_key.spki,
+            extensions,
+        }.sign(&self.key, &self.rng)?;
+
+        let issued = IssuedCertificate {
+            serial: x509::serial_hex(&serial),
+            device_id: device_id.clone(),
+            owner_id: self.devices.owner_of(&device_id),
+            fingerprint: x509::fingerprint(&certificate),
+            der: certificate,
+            key_algorithm: request.public_key.algorithm,
+            trust_level: assessment.trust_level,
+            protocol,
+            not_before,
+            not_after,
+            status: CertificateStatus::Active,
+            renews: renews.clone(),
+        };
+        self.certificates.insert(issued.serial.clone(), issued.clone());
+        self.by_device.entry(device_id.clone()).or_default().push(issued.serial.clone());
+        self.devices.add_certificate(&device_id, DeviceCertificate {
+            id: issued.serial.clone(),
+            subject: format!("CN={}, O={}", device_id, self.config.organization),
+            issuer: format!("CN={}, O={}", self.config.common_name, self.config.organization),
+            valid_from: not_before,
+            valid_until: not_after,
+            fingerprint: issued.fingerprint.clone(),
+        });
+
+        if let Some(previous) = &renews {
+            if let Some(mut old) = self.certificates.get_mut(previous) {
+                if old.status == CertificateStatus::Active {
+                    old.status = CertificateStatus::Superseded { by: issued.serial.clone(), at: now };
+                }
+            }
+            self.devices.remove_certificate(&device_id, previous);
+        }
+
+        let mut details = HashMap::new();
+        details.insert("serial".to_string(), issued.serial.clone());
+        details.insert("protocol".to_string(), format!("{:?}", protocol));
+        details.insert("trust_level".to_string(), format!("{:?}", issued.trust_level));
+        details.insert("not_after".to_string(), not_after.to_rfc3339());
+        let event_type = match &renews {
+            Some(previous) => {
+                details.insert("renews".to_string(), previous.clone());
+                AuditEventType::DeviceCertificateRenewed
+            }
+            None => AuditEventType::DeviceCertificateIssued,
+        };
+        self.audit.log_device_certificate(event_type, &device_id, &device_id, details).await;
+
+        tracing::info!(device_id = %device_id, serial = %issued.serial, "Device certificate issued");
+        Ok(issued)
+    }
+
+    /// RA certificate for the SCEP server's RSA key
+    pub(crate) fn issue_ra_certificate(&self, spki: &[u8], common_name: &str) -> Result<Vec<u8>, PkiError> {
+        let public_key = PublicKey::parse(der::parse(spki)?)?;
+        let subject = x509::name(common_name, Some(&self.config.organization));
+        TbsCertificate {
+            serial: &new_serial(&self.rng)?,
+            issuer: &self.subject,
+            subject: &subject,
+            not_before: Utc::now() - Duration::minutes(5),
+            not_after: self.not_after,
+            spki,
+            extensions: vec![
+                x509::extension(oid::BASIC_CONSTRAINTS, true, &der::sequence(&[])),
+                x509::extension(oid::KEY_USAGE, true, &der::named_bits(&[
+                    key_usage::DIGITAL_SIGNATURE,
+                    key_usage::KEY_ENCIPHERMENT,
+                ])),
+                x509::extension(oid::SUBJECT_KEY_ID, false, &der::octet_string(&public_key.key_id())),
+                x509::extension(oid::AUTHORITY_KEY_ID, false, &x509::authority_key_id(&self.key_id)),
+            ],
+        }.sign(&self.key, &self.rng)
+    }
+
+    // =========================================================================
+    // Verification
+    // =========================================================================
+
+    /// Authenticate a device by the certificate it presented
+    pub fn verify_peer(&self, certificate: &[u8]) -> Result<DeviceIdentity, PkiError> {
+        let parsed = Certificate::parse(certificate)?;
+        if parsed.issuer != self.subject.as_slice() {
+            return Err(PkiError::UnknownIssuer);
+        }
+        self.public_key.verify(&parsed.signature_algorithm, parsed.tbs, parsed.signature)?;
+
+        let now = Utc::now();
+        if now < parsed.not_before || now > parsed.not_after {
+            return Err(PkiError::Expired);
+        }
+
+        let serial = x509::serial_hex(parsed.serial);
+        let record = self.certificates.get(&serial)
+            .ok_or_else(|| PkiError::UnknownCertificate(serial.clone()))?;
+        if record.fingerprint != x509::fingerprint(certificate) {
+            return Err(PkiError::UnknownCertificate(serial));
+        }
+        match &record.status {
+            CertificateStatus::Active => {}
+            CertificateStatus::Superseded { at, .. } if now - *at <= self.config.rollover_grace => {}
+            CertificateStatus::Superseded { .. } => return Err(PkiError::Superseded),
+            CertificateStatus::Revoked { reason, .. } => return Err(PkiError::Revoked(*reason)),
+        }
+
+        Ok(DeviceIdentity {
+            device_id: record.device_id.clone(),
+            owner_id: record.owner_id.clone(),
+            serial: record.serial.clone(),
+            fingerprint: record.fingerprint.clone(),
+            trust_level: record.trust_level,
+            not_after: record.not_after,
+            renewal_due: record.renewal_due(self.config.renewal_threshold, now),
+        })
+    }
+
+    /// Check the certificate presented on a connection, if any
+    pub fn certificate_state(&self, certificate: Option<&[u8]>) -> DeviceCertificateState {
+        let Some(certificate) = certificate else {
+            return DeviceCertificateState::Missing;
+        };
+        match self.verify_peer(certificate) {
+            Ok(identity) => DeviceCertificateState::Verified(identity),
+            Err(PkiError::Revoked(reason)) => DeviceCertificateState::Revoked(reason),
+            Err(e) => DeviceCertificateState::Invalid(e.to_string()),
+        }
+    }
+
+    pub fn get(&self, serial: &str) -> Option<IssuedCertificate> {
+        self.certificates.get(serial).map(|c| c.clone())
+    }
+
+    pub fn certificates_for(&self, device_id: &str) -> Vec<IssuedCertificate> {
+        self.by_device.get(device_id)
+            .map(|serials| serials.iter().filter_map(|s| self.get(s)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Active certificates inside their renewal window, for pushing
+    /// renewals through device management
+    pub fn renewal_due(&self) -> Vec<IssuedCertificate> {
+        let now = Utc::now();
+        self.certificates.iter()
+            .filter(|c| c.status == CertificateStatus::Active && c.not_after > now)
+            .filter(|c| c.renewal_due(self.config.renewal_threshold, now))
+            .map(|c| c.clone())
+            .collect()
+    }
+
+    // =========================================================================
+    // Revocation
+    // =========================================================================
+
+    pub async fn revoke(
+        &self,
+        serial: &str,
+        reason: RevocationReason,
+        actor_id: &str,
+    ) -> Result<IssuedCertificate, PkiError> {
+        let revoked = {
+            let mut entry = self.certificates.get_mut(serial)
+                .ok_or_else(|| PkiError::UnknownCertificate(serial.to_string()))?;
+            if let CertificateStatus::Revoked { reason, .. } = entry.status {
+                return Err(PkiError::Revoked(reason));
+            }
+            entry.status = CertificateStatus::Revoked { reason, at: Utc::now() };
+            entry.clone()
+        };
+        self.devices.remove_certificate(&revoked.device_id, serial);
+
+        let mut details = HashMap::new();
+        details.insert("serial".to_string(), serial.to_string());
+        details.insert("reason".to_string(), format!("{:?}", reason));
+        self.audit.log_device_certificate(AuditEventType::DeviceCertificateRevoked, actor_id, &revoked.device_id, details).await;
+
+        tracing::warn!(device_id = %revoked.device_id, serial = %serial, ?reason, "Device certificate revoked");
+        Ok(revoked)
+    }
+
+    /// Revoke every unexpired certificate a device holds. Returns the
+    /// serials revoked.
+    pub async fn revoke_device(&self, device_id: &str, reason: RevocationReason, actor_id: &str) -> Vec<String> {
+        let now = Utc::now();
+        let serials: Vec<String> = self.certificates_for(device_id).into_iter()
+            .filter(|c| !c.is_revoked() && c.not_after > now)
+            .map(|c| c.serial)
+            .collect();
+
+        let mut revoked = Vec::new();
+        for serial in serials {
+            if self.revoke(&serial, reason, actor_id).await.is_ok() {
+                revoked.push(serial);
+            }
+        }
+        revoked
+    }
+
+    /// Revoke a device's certificates when a signal says it or its
+    /// credentials are compromised
+    pub async fn handle_risk_signal(&self, device_id: &str, signal: &RiskSignal) -> Vec<String> {
+        let compromised = match signal.signal_type {
+            RiskSignalType::MalwareDetected => signal.severity != RiskSeverity::Low,
+            RiskSignalType::CompromisedCredential => {
+                matches!(signal.severity, RiskSeverity::High | RiskSeverity::Critical)
+            }
+            _ => false,
+        };
+        if !compromised {
+            return Vec::new();
+        }
+        self.revoke_device(device_id, RevocationReason::KeyCompromise, "system").await
+    }
+
+    /// Re-check posture after it changed and revoke if the device no longer
+    /// qualifies
+    pub async fn reassess(&self, device_id: &str) -> Vec<String> {
+        let Some(device) = self.devices.get(device_id) else {
+            return self.revoke_device(device_id, RevocationReason::CessationOfOperation, "system").await;
+        };
+        let assessment = self.devices.assess(&device);
+        if assessment.trust_level >= self.config.minimum_trust
+            && (assessment.compliant || !self.config.require_compliant)
+        {
+            return Vec::new();
+        }
+        self.revoke_device(device_id, RevocationReason::PrivilegeWithdrawn, "system").await
+    }
+
+    /// Signed CRL of revoked certificates that have not yet expired
+    pub fn crl(&self) -> Result<Vec<u8>, PkiError> {
+        let now = Utc::now();
+        let entries: Vec<Vec<u8>> = self.certificates.iter()
+            .filter(|c| c.not_after > now)
+            .filter_map(|c| match c.status {
+                CertificateStatus::Revoked { reason, at } => Some(der::sequence(&[
+                    &der::unsigned(&hex_bytes(&c.serial)),
+                    &der::time(at),
+                    &der::sequence(&[&x509::extension(
+                        oid::CRL_REASON,
+                        false,
+                        &der::tlv(der::ENUMERATED, &[reason.code()]),
+                    )]),
+                ])),
+                _ => None,
+            })
+            .collect();
+
+        let number = self.crl_number.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut fields = vec![
+            der::integer(1),
+            der::algorithm(oid::ECDSA_SHA256),
+            self.subject.clone(),
+            der::time(now),
+            der::time(now + self.config.crl_validity),
+        ];
+        if !entries.is_empty() {
+            fields.push(der::tlv(der::SEQUENCE, &entries.concat()));
+        }
+        fields.push(der::explicit(0, &der::sequence(&[
+            &x509::extension(oid::AUTHORITY_KEY_ID, false, &x509::authority_key_id(&self.key_id)),
+            &x509::extension(oid::CRL_NUMBER, false, &der::integer(number)),
+        ])));
+
+        x509::sign(&der::tlv(der::SEQUENCE, &fields.concat()), &self.key, &self.rng)
+    }
+
+    /// Drop expired certificates and enrollment tokens. Returns how many
+    /// certificates were removed.
+    pub fn purge_expired(&self) -> usize {
+        let now = Utc::now();
+        self.tokens.retain(|_, token| token.expires_at > now);
+
+        let expired: Vec<(String, String)> = self.certificates.iter()
+            .filter(|c| c.not_after <= now)
+            .map(|c| (c.serial.clone(), c.device_id.clone()))
+            .collect();
+        for (serial, device_id) in &expired {
+            self.certificates.remove(serial);
+            if let Some(mut serials) = self.by_device.get_mut(device_id) {
+                serials.retain(|s| s != serial);
+            }
+            self.devices.remove_certificate(device_id, serial);
+        }
+        self.by_device.retain(|_, serials| !serials.is_empty());
+        expired.len()
+    }
+
+    pub fn stats(&self) -> PkiStats {
+        let now = Utc::now();
+        let mut stats = PkiStats {
+            pending_tokens: self.tokens.len(),
+            ..Default::default()
+        };
+        for certificate in self.certificates.iter() {
+            match certificate.status {
+                CertificateStatus::Revoked { .. } => stats.revoked += 1,
+                _ if certificate.not_after <= now => stats.expired += 1,
+                CertificateStatus::Superseded { .. } => stats.superseded += 1,
+                CertificateStatus::Active => stats.active += 1,
+            }
+        }
+        stats
+    }
+}
+
+/// Random positive 128-bit serial (RFC 5280 allows up to 20 octets)
+fn new_serial(rng: &SystemRandom) -> Result<[u8; 16], PkiError> {
+    let mut serial = [0u8; 16];
+    rng.fill(&mut serial)
+        .map_err(|_| PkiError::Internal("random generation failed".to_string()))?;
+    serial[0] = (serial[0] & 0x7F) | 0x40;
+    Ok(serial)
+}
+
+fn token_hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len() / 2)
+        .filter_map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+        .collect()
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub enum PkiError {
+    Malformed(String),
+    UnsupportedKey(String),
+    BadSignature,
+    /// Enrollment token unknown, spent or expired
+    InvalidCredentials,
+    DeviceNotRegistered(String),
+    /// Posture issues that kept the device from a certificate
+    PostureDenied(Vec<String>),
+    UnknownIssuer,
+    UnknownCertificate(String),
+    Expired,
+    Superseded,
+    Revoked(RevocationReason),
+    Internal(String),
+}
+
+impl std::fmt::Display for PkiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(what) => write!(f, "Malformed PKI message: {}", what),
+            Self::UnsupportedKey(what) => write!(f, "Unsupported algorithm: {}", what),
+            Self::BadSignature => write!(f, "Signature verification failed"),
+            Self::InvalidCredentials => write!(f, "Invalid or expired enrollment credentials"),
+            Self::DeviceNotRegistered(id) => write!(f, "Device not registered: {}", id),
+            Self::PostureDenied(issues) => write!(f, "Device posture does not allow enrollment: {}", issues.join("; ")),
+            Self::UnknownIssuer => write!(f, "Certificate was not issued by the device CA"),
+            Self::UnknownCertificate(serial) => write!(f, "Unknown certificate: {}", serial),
+            Self::Expired => write!(f, "Certificate is outside its validity period"),
+            Self::Superseded => write!(f, "Certificate has been replaced"),
+            Self::Revoked(reason) => write!(f, "Certificate revoked ({:?})", reason),
+            Self::Internal(what) => write!(f, "Internal CA error: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for PkiError {}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Device, DevicePosture, DeviceType};
+
+    fn posture(healthy: bool) -> DevicePosture {
+        DevicePosture {
+            firewall_enabled: healthy,
+            antivirus_running: true,
+            disk_encrypted: true,
+            os_patched: true,
+            screen_lock_enabled: true,
+            jailbroken: false,
+            last_checked: Utc::now(),
+            antivirus_products: vec![],
+            os_patch_age_days: None,
+            attributes: HashMap::new(),
+        }
+    }
+
+    fn device(id: &str, healthy: bool) -> Device {
+        Device {
+            id: id.to_string(),
+            name: id.to_string(),
+            device_type: DeviceType::Laptop,
+            os: "macOS".to_string(),
+            os_version: "14.0".to_string(),
+            managed: true,
+            compliant: true,
+            trust_level: TrustLevel::High,
+            posture: posture(healthy),
+            certificates: vec![],
+            last_seen: Utc::now(),
+        }
+    }
+
+    fn new_ca_with(config: CaConfig) -> (DeviceCa, Arc<DeviceAssessor>) {
+        let devices = Arc::new(DeviceAssessor::new());
+        devices.register("alice", device("laptop-1", true));
+        devices.register("bob", device("laptop-2", true));
+        let ca = DeviceCa::new(
+            config,
+            &DeviceCa::generate_key().unwrap(),
+            None,
+            devices.clone(),
+            Arc::new(AuditLogger::new()),
+        ).unwrap();
+        (ca, devices)
+    }
+
+    fn new_ca() -> (DeviceCa, Arc<DeviceAssessor>) {
+        new_ca_with(CaConfig::default())
+    }
+
+    /// PKCS#10 request for a fresh P-256 key
+    fn csr() -> Vec<u8> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let spki = der::sequence(&[
+            &der::sequence(&[&der::oid(oid::EC_PUBLIC_KEY), &der::oid(oid::P256)]),
+            &der::bit_string(key.public_key().as_ref()),
+        ]);
+        let info = der::sequence(&[
+            &der::integer(0),
+            &x509::name("ignored", None),
+            &spki,
+            &der::tlv(0xA0, &[]),
+        ]);
+        x509::sign(&info, &key, &rng).unwrap()
+    }
+
+    async fn enroll(ca: &DeviceCa, device_id: &str) -> IssuedCertificate {
+        let token = ca.create_enrollment_token(device_id).unwrap();
+        let auth = EnrollmentAuth::Token { device_id: Some(device_id), token: &token };
+        ca.enroll(auth, &csr(), EnrollmentProtocol::Api).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issued_certificate_parses_and_verifies() {
+        let (ca, devices) = new_ca();
+        let issued = enroll(&ca, "laptop-1").await;
+        assert_eq!(issued.owner_id.as_deref(), Some("alice"));
+        assert_eq!(devices.get("laptop-1").unwrap().certificates.len(), 1);
+
+        let (rest, parsed) = x509_parser::parse_x509_certificate(&issued.der).unwrap();
+        assert!(rest.is_empty());
+        let (_, root) = x509_parser::parse_x509_certificate(ca.certificate()).unwrap();
+        assert_eq!(parsed.issuer(), root.subject());
+        assert_eq!(parsed.subject().iter_common_name().next().unwrap().as_str().unwrap(), "laptop-1");
+        assert_eq!(parsed.raw_serial_as_string().replace(':', ""), issued.serial);
+        assert!(!parsed.is_ca());
+
+        // What a TLS server asking for device certificates accepts
+        let verifier = ca.client_verifier().unwrap();
+        verifier
+            .verify_client_cert(&rustls::Certificate(issued.der.clone()), &[], std::time::SystemTime::now())
+            .unwrap();
+
+        let identity = ca.verify_peer(&issued.der).unwrap();
+        assert_eq!(identity.device_id, "laptop-1");
+        assert_eq!(identity.serial, issued.serial);
+        assert!(!identity.renewal_due);
+    }
+
+    #[tokio::test]
+    async fn test_enroll_rejects_expired_token() {
+        let (ca, _) = new_ca_with(CaConfig {
+            enrollment_token_ttl: Duration::seconds(-1),
+            ..CaConfig::default()
+        });
+        let token = ca.create_enrollment_token("laptop-1").unwrap();
+
+        let auth = EnrollmentAuth::Token { device_id: Some("laptop-1"), token: &token };
+        let result = ca.enroll(auth, &csr(), EnrollmentProtocol::Est).await;
+        assert!(matches!(result, Err(PkiError::InvalidCredentials)));
+        assert_eq!(ca.stats().pending_tokens, 0);
+    }
+
+    #[tokio::test]
+    async fn test_enroll_rejects_token_of_another_device() {
+        let (ca, _) = new_ca();
+        let token = ca.create_enrollment_token("laptop-1").unwrap();
+
+        let auth = EnrollmentAuth::Token { device_id: Some("laptop-2"), token: &token };
+        let result = ca.enroll(auth, &csr(), EnrollmentProtocol::Est).await;
+        assert!(matches!(result, Err(PkiError::InvalidCredentials)));
+        assert!(ca.certificates_for("laptop-2").is_empty());
+        assert!(matches!(ca.create_enrollment_token("laptop-9"), Err(PkiError::DeviceNotRegistered(_))));
+    }
+
+    #[tokio::test]
+    async fn test_token_is_spent_by_posture_denial() {
+        let (ca, devices) = new_ca();
+        devices.update_posture("laptop-1", posture(false));
+        let token = ca.create_enrollment_token("laptop-1").unwrap();
+        let auth = EnrollmentAuth::Token { device_id: Some("laptop-1"), token: &token };
+
+        let denied = ca.enroll(auth, &csr(), EnrollmentProtocol::Est).await;
+        assert!(matches!(denied, Err(PkiError::PostureDenied(_))));
+
+        // Remediated, but the token is gone
+        devices.update_posture("laptop-1", posture(true));
+        let reused = ca.enroll(auth, &csr(), EnrollmentProtocol::Est).await;
+        assert!(matches!(reused, Err(PkiError::InvalidCredentials)));
+        assert!(ca.certificates_for("laptop-1").is_empty());
+
+        enroll(&ca, "laptop-1").await;
+    }
+
+    #[tokio::test]
+    async fn test_token_is_single_use() {
+        let (ca, _) = new_ca();
+        let token = ca.create_enrollment_token("laptop-1").unwrap();
+        let auth = EnrollmentAuth::Token { device_id: Some("laptop-1"), token: &token };
+
+        ca.enroll(auth, &csr(), EnrollmentProtocol::Est).await.unwrap();
+        let again = ca.enroll(auth, &csr(), EnrollmentProtocol::Est).await;
+        assert!(matches!(again, Err(PkiError::InvalidCredentials)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_peer_rejects_other_issuer() {
+        let (ca, _) = new_ca();
+
+        let (other, _) = new_ca_with(CaConfig { common_name: "Other CA".to_string(), ..CaConfig::default() });
+        let foreign = enroll(&other, "laptop-1").await;
+        assert!(matches!(ca.verify_peer(&foreign.der), Err(PkiError::UnknownIssuer)));
+
+        // Same name, different key
+        let (impostor, _) = new_ca();
+        let forged = enroll(&impostor, "laptop-1").await;
+        assert!(matches!(ca.verify_peer(&forged.der), Err(PkiError::BadSignature)));
+        assert!(matches!(ca.certificate_state(Some(&forged.der)), DeviceCertificateState::Invalid(_)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_peer_rejects_tampered_certificate() {
+        let (ca, _) = new_ca();
+        let issued = enroll(&ca, "laptop-1").await;
+
+        // Claim the other laptop inside the signed part
+        let mut tampered = issued.der.clone();
+        let at = tampered.windows(8).position(|w| w == b"laptop-1").unwrap();
+        tampered[at + 7] = b'2';
+        assert!(matches!(ca.verify_peer(&tampered), Err(PkiError::BadSignature)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_peer_rejects_revoked_certificate() {
+        let (ca, devices) = new_ca();
+        let issued = enroll(&ca, "laptop-1").await;
+
+        ca.revoke(&issued.serial, RevocationReason::KeyCompromise, "admin").await.unwrap();
+        assert!(matches!(ca.verify_peer(&issued.der), Err(PkiError::Revoked(RevocationReason::KeyCompromise))));
+        assert!(matches!(
+            ca.certificate_state(Some(&issued.der)),
+            DeviceCertificateState::Revoked(RevocationReason::KeyCompromise)
+        ));
+        assert!(devices.get("laptop-1").unwrap().certificates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_superseded_certificate_works_only_within_grace() {
+        let (ca, _) = new_ca();
+        let old = enroll(&ca, "laptop-1").await;
+        let renewed = ca.enroll(EnrollmentAuth::Certificate(&old.der), &csr(), EnrollmentProtocol::Est).await.unwrap();
+        assert_eq!(renewed.renews.as_deref(), Some(old.serial.as_str()));
+        assert!(ca.verify_peer(&old.der).is_ok());
+        assert!(ca.verify_peer(&renewed.der).is_ok());
+
+        let (ca, _) = new_ca_with(CaConfig { rollover_grace: Duration::zero(), ..CaConfig::default() });
+        let old = enroll(&ca, "laptop-1").await;
+        ca.enroll(EnrollmentAuth::Certificate(&old.der), &csr(), EnrollmentProtocol::Est).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert!(matches!(ca.verify_peer(&old.der), Err(PkiError::Superseded)));
+
+        // Nor can it be used to renew again
+        let result = ca.enroll(EnrollmentAuth::Certificate(&old.der), &csr(), EnrollmentProtocol::Est).await;
+        assert!(matches!(result, Err(PkiError::Superseded)));
+    }
+
+    #[tokio::test]
+    async fn test_crl_parses_and_lists_revocations() {
+        let (ca, _) = new_ca();
+        let revoked = enroll(&ca, "laptop-1").await;
+        let kept = enroll(&ca, "laptop-2").await;
+        ca.revoke(&revoked.serial, RevocationReason::KeyCompromise, "admin").await.unwrap();
+
+        let der = ca.crl().unwrap();
+        let (rest, crl) = x509_parser::parse_x509_crl(&der).unwrap();
+        assert!(rest.is_empty());
+        let (_, root) = x509_parser::parse_x509_certificate(ca.certificate()).unwrap();
+        assert_eq!(crl.issuer(), root.subject());
+        assert_eq!(crl.crl_number().map(|n| n.to_string()).as_deref(), Some("1"));
+
+        let entries: Vec<_> = crl.iter_revoked_certificates().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].raw_serial_as_string().replace(':', ""), revoked.serial);
+        assert_ne!(entries[0].raw_serial_as_string().replace(':', ""), kept.serial);
+        assert_eq!(entries[0].reason_code().map(|(_, code)| code.0), Some(1));
+
+        ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ECDSA_P256_SHA256_ASN1,
+            root.public_key().subject_public_key.data.as_ref(),
+        )
+        .verify(crl.tbs_cert_list.as_ref(), crl.signature_value.data.as_ref())
+        .unwrap();
+
+        // Without revocations the list is left out, and numbers keep rising
+        let (ca, _) = new_ca();
+        ca.crl().unwrap();
+        let der = ca.crl().unwrap();
+        let (_, empty) = x509_parser::parse_x509_crl(&der).unwrap();
+        assert_eq!(empty.iter_revoked_certificates().count(), 0);
+        assert_eq!(empty.crl_number().map(|n| n.to_string()).as_deref(), Some("2"));
+    }
+}
@@ -0,0 +1,247 @@
+//! SCEP enrollment (RFC 8894)
+//!
+//! For MDM-managed devices and network gear that only speak SCEP. Requests
+//! are encrypted to, and responses signed by, an RSA registration
+//! authority key whose certificate the device CA issues at startup.
+//!
+//! - PKCSReq: first certificate, authenticated by the enrollment token in
+//!   the CSR's challengePassword
+//! - RenewalReq: signed with the device's current certificate
+//! - CertPoll: repeats the answer for a transaction already issued
+//!
+//! Certificates are issued or refused synchronously, so there is no
+//! PENDING state.
+
+use super::der::{self, oid};
+use super::x509::{Certificate, CertificationRequest};
+use super::{cms, DeviceCa, EnrollmentAuth, EnrollmentProtocol, PkiError};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use ring::signature::RsaKeyPair;
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use std::sync::Arc;
+
+/// Answers kept for CertPoll and retransmitted requests
+const TRANSACTION_RETENTION_HOURS: i64 = 24;
+
+pub struct ScepRequest<'a> {
+    pub method: &'a str,
+    /// The `operation` query parameter
+    pub operation: &'a str,
+    /// POST body, or for GET the URL-decoded `message` parameter
+    pub message: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct ScepResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl ScepResponse {
+    fn error(status: u16, message: &str) -> Self {
+        Self { status, content_type: "text/plain", body: message.as_bytes().to_vec() }
+    }
+}
+
+/// SCEP failInfo values, named as in RFC 8894
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
+enum FailInfo {
+    BadAlg = 0,
+    BadMessageCheck = 1,
+    BadRequest = 2,
+    BadTime = 3,
+    BadCertId = 4,
+}
+
+impl From<&PkiError> for FailInfo {
+    fn from(error: &PkiError) -> Self {
+        match error {
+            PkiError::UnsupportedKey(_) => Self::BadAlg,
+            PkiError::Malformed(_) | PkiError::BadSignature => Self::BadMessageCheck,
+            PkiError::Expired => Self::BadTime,
+            PkiError::UnknownIssuer
+            | PkiError::UnknownCertificate(_)
+            | PkiError::Superseded
+            | PkiError::Revoked(_) => Self::BadCertId,
+            _ => Self::BadRequest,
+        }
+    }
+}
+
+pub struct ScepServer {
+    ca: Arc<DeviceCa>,
+    ra_certificate: Vec<u8>,
+    ra_signing: RsaKeyPair,
+    ra_decryption: RsaPrivateKey,
+    /// Issued certificate by transaction id
+    transactions: dashmap::DashMap<String, (DateTime<Utc>, Vec<u8>)>,
+}
+
+impl ScepServer {
+    /// New 2048-bit RSA registration authority key as PKCS#8
+    pub fn generate_ra_key() -> Result<Vec<u8>, PkiError> {
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048)
+            .map_err(|e| PkiError::Internal(format!("RA key generation failed: {}", e)))?;
+        key.to_pkcs8_der()
+            .map(|der| der.as_bytes().to_vec())
+            .map_err(|e| PkiError::Internal(format!("RA key encoding failed: {}", e)))
+    }
+
+    pub fn new(ca: Arc<DeviceCa>, ra_key_pkcs8: &[u8]) -> Result<Self, PkiError> {
+        let ra_decryption = RsaPrivateKey::from_pkcs8_der(ra_key_pkcs8)
+            .map_err(|_| PkiError::UnsupportedKey("RA key must be RSA PKCS#8".to_string()))?;
+        let ra_signing = RsaKeyPair::from_pkcs8(ra_key_pkcs8)
+            .map_err(|e| PkiError::UnsupportedKey(format!("RA key rejected: {}", e)))?;
+        let spki = ra_decryption.to_public_key().to_public_key_der()
+            .map_err(|e| PkiError::Internal(format!("RA key encoding failed: {}", e)))?;
+        let ra_certificate = ca.issue_ra_certificate(spki.as_bytes(), &format!("{} SCEP RA", ca.config().common_name))?;
+
+        Ok(Self {
+            ca,
+            ra_certificate,
+            ra_signing,
+            ra_decryption,
+            transactions: dashmap::DashMap::new(),
+        })
+    }
+
+    pub async fn handle(&self, request: &ScepRequest<'_>) -> ScepResponse {
+        match request.operation {
+            "GetCACaps" => ScepResponse {
+                status: 200,
+                content_type: "text/plain",
+                body: b"POSTPKIOperation\nRenewal\nSHA-256\nAES\nSCEPStandard\n".to_vec(),
+            },
+            "GetCACert" => ScepResponse {
+                status: 200,
+                content_type: "application/x-x509-ca-ra-cert",
+                body: cms::certs_only(&[self.ca.certificate(), self.ra_certificate.as_slice()]),
+            },
+            "PKIOperation" => {
+                let message = if request.method.eq_ignore_ascii_case("GET") {
+                    match base64::engine::general_purpose::STANDARD.decode(request.message) {
+                        Ok(message) => message,
+                        Err(_) => return ScepResponse::error(400, "message is not base64"),
+                    }
+                } else {
+                    request.message.to_vec()
+                };
+                self.pki_operation(&message).await
+            }
+            "GetNextCACert" => ScepResponse::error(501, "CA rollover is not supported"),
+            _ => ScepResponse::error(400, "Unknown operation"),
+        }
+    }
+
+    async fn pki_operation(&self, message: &[u8]) -> ScepResponse {
+        // Without a verified signer there is nobody to answer to
+        let signed = match cms::SignedMessage::open(message) {
+            Ok(signed) => signed,
+            Err(e) => return ScepResponse::error(400, &e.to_string()),
+        };
+        let text = |id| signed.attribute(id).map(|v| String::from_utf8_lossy(v).into_owned());
+        let (Some(transaction_id), Some(message_type)) = (text(oid::SCEP_TRANSACTION_ID), text(oid::SCEP_MESSAGE_TYPE)) else {
+            return ScepResponse::error(400, "Missing transactionID or messageType");
+        };
+        let sender_nonce = signed.attribute(oid::SCEP_SENDER_NONCE).unwrap_or_default();
+
+        let now = Utc::now();
+        let retention = Duration::hours(TRANSACTION_RETENTION_HOURS);
+        self.transactions.retain(|_, (at, _)| now - *at < retention);
+
+        let answered = self.transactions.get(&transaction_id).map(|entry| entry.1.clone());
+        let result = match answered {
+            // Retransmission or CertPoll for a transaction already answered
+            Some(certificate) => Ok(certificate),
+            None => match message_type.as_str() {
+                "19" => self.enroll(&signed, false).await,
+                "17" => self.enroll(&signed, true).await,
+                _ => Err(FailInfo::BadRequest),
+            },
+        };
+        if let Ok(certificate) = &result {
+            self.transactions.insert(transaction_id.clone(), (now, certificate.clone()));
+        }
+
+        match self.cert_rep(result, &transaction_id, sender_nonce, &signed.signer) {
+            Ok(body) => ScepResponse { status: 200, content_type: "application/x-pki-message", body },
+            Err(e) => ScepResponse::error(500, &e.to_string()),
+        }
+    }
+
+    async fn enroll(&self, signed: &cms::SignedMessage, renewal: bool) -> Result<Vec<u8>, FailInfo> {
+        let csr = cms::decrypt(&signed.content, &self.ra_decryption)
+            .map_err(|e| FailInfo::from(&e))?;
+        let request = CertificationRequest::parse(&csr).map_err(|e| FailInfo::from(&e))?;
+
+        let password;
+        let auth = if renewal {
+            EnrollmentAuth::Certificate(&signed.signer)
+        } else {
+            // PKCSReq is signed with a throwaway certificate for the
+            // requested key, which proves the sender holds it
+            let signer = Certificate::parse(&signed.signer).map_err(|e| FailInfo::from(&e))?;
+            if signer.public_key.spki != request.public_key.spki {
+                return Err(FailInfo::BadMessageCheck);
+            }
+            password = request.challenge_password.ok_or(FailInfo::BadRequest)?;
+            EnrollmentAuth::Token { device_id: None, token: &password }
+        };
+
+        match self.ca.enroll(auth, &csr, EnrollmentProtocol::Scep).await {
+            Ok(issued) => Ok(issued.der),
+            Err(e) => {
+                tracing::warn!(error = %e, renewal, "SCEP enrollment refused");
+                Err(FailInfo::from(&e))
+            }
+        }
+    }
+
+    /// CertRep: the certificate encrypted to the requester, or a failure
+    fn cert_rep(
+        &self,
+        result: Result<Vec<u8>, FailInfo>,
+        transaction_id: &str,
+        recipient_nonce: &[u8],
+        recipient: &[u8],
+    ) -> Result<Vec<u8>, PkiError> {
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mut attributes = vec![
+            cms::attribute(oid::SCEP_MESSAGE_TYPE, &der::printable("3")),
+            cms::attribute(oid::SCEP_TRANSACTION_ID, &der::printable(transaction_id)),
+            cms::attribute(oid::SCEP_SENDER_NONCE, &der::octet_string(&nonce)),
+            cms::attribute(oid::SCEP_RECIPIENT_NONCE, &der::octet_string(recipient_nonce)),
+        ];
+
+        let enveloped = result.and_then(|certificate| {
+            cms::encrypt(&cms::certs_only(&[certificate.as_slice()]), recipient)
+                .map_err(|e| FailInfo::from(&e))
+        });
+        let content = match enveloped {
+            Ok(enveloped) => {
+                attributes.push(cms::attribute(oid::SCEP_PKI_STATUS, &der::printable("0")));
+                Some(enveloped)
+            }
+            Err(fail) => {
+                attributes.push(cms::attribute(oid::SCEP_PKI_STATUS, &der::printable("2")));
+                attributes.push(cms::attribute(oid::SCEP_FAIL_INFO, &der::printable(&(fail as u8).to_string())));
+                None
+            }
+        };
+
+        cms::sign_rsa(
+            content.as_deref(),
+            attributes,
+            &self.ra_certificate,
+            &self.ra_signing,
+            self.ra_decryption.size(),
+        )
+    }
+}
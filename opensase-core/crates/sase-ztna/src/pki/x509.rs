@@ -0,0 +1,286 @@
+//! Certificates, CSRs and CRLs
+//!
+//! Parsing covers what the CA checks: the CSR's key and proof of
+//! possession, and for presented certificates the issuer, serial, validity
+//! and signature. Building produces the CA, RA and device certificates and
+//! the CRL, all signed with the CA's P-256 key.
+
+use super::der::{self, oid, Tlv};
+use super::PkiError;
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{self, EcdsaKeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Public key algorithms accepted for device keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyAlgorithm {
+    EcP256,
+    EcP384,
+    Ed25519,
+    Rsa { bits: usize },
+}
+
+/// SubjectPublicKeyInfo
+#[derive(Clone)]
+pub(crate) struct PublicKey {
+    pub algorithm: KeyAlgorithm,
+    /// Full SubjectPublicKeyInfo encoding
+    pub spki: Vec<u8>,
+    /// The subjectPublicKey bits: an EC point, RSAPublicKey or Ed25519 key
+    pub key: Vec<u8>,
+}
+
+impl PublicKey {
+    pub fn parse(spki: Tlv<'_>) -> Result<Self, PkiError> {
+        let fields = spki.expect(der::SEQUENCE)?.children()?;
+        let [algorithm, key] = fields.as_slice() else {
+            return Err(PkiError::Malformed("bad SubjectPublicKeyInfo".to_string()));
+        };
+        let parts = algorithm.expect(der::SEQUENCE)?.children()?;
+        let id = parts.first()
+            .ok_or_else(|| PkiError::Malformed("empty key algorithm".to_string()))?
+            .oid()?;
+        let key = key.bits()?.to_vec();
+
+        let algorithm = match id.as_str() {
+            oid::EC_PUBLIC_KEY => match parts.get(1).map(|p| p.oid()).transpose()?.as_deref() {
+                Some(oid::P256) => KeyAlgorithm::EcP256,
+                Some(oid::P384) => KeyAlgorithm::EcP384,
+                other => return Err(PkiError::UnsupportedKey(format!("EC curve {:?}", other))),
+            },
+            oid::ED25519 => KeyAlgorithm::Ed25519,
+            oid::RSA_ENCRYPTION => {
+                let rsa = der::parse(&key)?.expect(der::SEQUENCE)?.children()?;
+                let modulus = rsa.first()
+                    .ok_or_else(|| PkiError::Malformed("bad RSA key".to_string()))?
+                    .value;
+                let significant = modulus.iter().skip_while(|b| **b == 0).count();
+                KeyAlgorithm::Rsa { bits: significant * 8 }
+            }
+            other => return Err(PkiError::UnsupportedKey(other.to_string())),
+        };
+        Ok(Self { algorithm, spki: spki.raw.to_vec(), key })
+    }
+
+    /// Verify a signature made with this key
+    pub fn verify(&self, signature_algorithm: &str, message: &[u8], sig: &[u8]) -> Result<(), PkiError> {
+        let algorithm: &dyn signature::VerificationAlgorithm = match (self.algorithm, signature_algorithm) {
+            (KeyAlgorithm::EcP256, oid::ECDSA_SHA256) => &signature::ECDSA_P256_SHA256_ASN1,
+            (KeyAlgorithm::EcP256, oid::ECDSA_SHA384) => &signature::ECDSA_P256_SHA384_ASN1,
+            (KeyAlgorithm::EcP384, oid::ECDSA_SHA256) => &signature::ECDSA_P384_SHA256_ASN1,
+            (KeyAlgorithm::EcP384, oid::ECDSA_SHA384) => &signature::ECDSA_P384_SHA384_ASN1,
+            (KeyAlgorithm::Ed25519, oid::ED25519) => &signature::ED25519,
+            (KeyAlgorithm::Rsa { .. }, oid::RSA_SHA256) => &signature::RSA_PKCS1_2048_8192_SHA256,
+            (KeyAlgorithm::Rsa { .. }, oid::RSA_SHA384) => &signature::RSA_PKCS1_2048_8192_SHA384,
+            (KeyAlgorithm::Rsa { .. }, oid::RSA_SHA512) => &signature::RSA_PKCS1_2048_8192_SHA512,
+            (_, other) => return Err(PkiError::UnsupportedKey(format!("signature algorithm {}", other))),
+        };
+        UnparsedPublicKey::new(algorithm, &self.key)
+            .verify(message, sig)
+            .map_err(|_| PkiError::BadSignature)
+    }
+
+    /// Key identifier: the first 160 bits of SHA-256 over the key
+    pub fn key_id(&self) -> Vec<u8> {
+        Sha256::digest(&self.key)[..20].to_vec()
+    }
+}
+
+// =============================================================================
+// Certification Requests
+// =============================================================================
+
+/// A PKCS#10 request whose signature has been checked
+pub(crate) struct CertificationRequest {
+    pub public_key: PublicKey,
+    /// SCEP carries the enrollment secret here
+    pub challenge_password: Option<String>,
+}
+
+impl CertificationRequest {
+    pub fn parse(data: &[u8]) -> Result<Self, PkiError> {
+        let outer = der::parse(data)?.expect(der::SEQUENCE)?.children()?;
+        let [info, algorithm, sig] = outer.as_slice() else {
+            return Err(PkiError::Malformed("bad certification request".to_string()));
+        };
+        let fields = info.expect(der::SEQUENCE)?.children()?;
+        if fields.len() < 3 {
+            return Err(PkiError::Malformed("bad certification request info".to_string()));
+        }
+
+        let public_key = PublicKey::parse(fields[2])?;
+        let algorithm = algorithm.expect(der::SEQUENCE)?.children()?
+            .first()
+            .ok_or_else(|| PkiError::Malformed("empty signature algorithm".to_string()))?
+            .oid()?;
+        // Proof of possession
+        public_key.verify(&algorithm, info.raw, sig.bits()?)?;
+
+        let mut challenge_password = None;
+        if let Some(attributes) = fields.get(3).filter(|a| a.tag == 0xA0) {
+            for attribute in attributes.children()? {
+                let parts = attribute.expect(der::SEQUENCE)?.children()?;
+                if parts.len() == 2 && parts[0].oid()? == oid::CHALLENGE_PASSWORD {
+                    challenge_password = parts[1].children()?.first().map(|v| v.string()).transpose()?;
+                }
+            }
+        }
+
+        Ok(Self {
+            public_key,
+            challenge_password,
+        })
+    }
+}
+
+// =============================================================================
+// Certificates
+// =============================================================================
+
+/// The parts of a certificate the CA checks
+pub(crate) struct Certificate<'a> {
+    pub tbs: &'a [u8],
+    pub serial: &'a [u8],
+    pub issuer: &'a [u8],
+    pub subject: &'a [u8],
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub public_key: PublicKey,
+    pub signature_algorithm: String,
+    pub signature: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PkiError> {
+        let outer = der::parse(data)?.expect(der::SEQUENCE)?.children()?;
+        let [tbs, algorithm, sig] = outer.as_slice() else {
+            return Err(PkiError::Malformed("bad certificate".to_string()));
+        };
+        let mut fields = tbs.expect(der::SEQUENCE)?.children()?;
+        if fields.first().map(|f| f.tag) == Some(0xA0) {
+            fields.remove(0);
+        }
+        if fields.len() < 6 {
+            return Err(PkiError::Malformed("bad TBSCertificate".to_string()));
+        }
+        let validity = fields[3].expect(der::SEQUENCE)?.children()?;
+        if validity.len() != 2 {
+            return Err(PkiError::Malformed("bad validity".to_string()));
+        }
+
+        Ok(Self {
+            tbs: tbs.raw,
+            serial: fields[0].expect(der::INTEGER)?.value,
+            issuer: fields[2].raw,
+            subject: fields[4].raw,
+            not_before: validity[0].time()?,
+            not_after: validity[1].time()?,
+            public_key: PublicKey::parse(fields[5])?,
+            signature_algorithm: algorithm.expect(der::SEQUENCE)?.children()?
+                .first()
+                .ok_or_else(|| PkiError::Malformed("empty signature algorithm".to_string()))?
+                .oid()?,
+            signature: sig.bits()?,
+        })
+    }
+
+    /// IssuerAndSerialNumber, for CMS recipient and signer identifiers
+    pub fn issuer_and_serial(&self) -> Vec<u8> {
+        der::sequence(&[self.issuer, &der::tlv(der::INTEGER, self.serial)])
+    }
+}
+
+/// Serial numbers as lowercase hex without leading zeros
+pub(crate) fn serial_hex(serial: &[u8]) -> String {
+    let serial = match serial.iter().position(|b| *b != 0) {
+        Some(start) => &serial[start..],
+        None => &serial[serial.len().saturating_sub(1)..],
+    };
+    serial.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// =============================================================================
+// Building
+// =============================================================================
+
+/// Name with a CN and optional O
+pub(crate) fn name(common_name: &str, organization: Option<&str>) -> Vec<u8> {
+    let rdn = |attribute: &str, value: &str| {
+        der::set(&[&der::sequence(&[&der::oid(attribute), &der::utf8(value)])])
+    };
+    let mut rdns = Vec::new();
+    if let Some(organization) = organization {
+        rdns.push(rdn(oid::ORGANIZATION, organization));
+    }
+    rdns.push(rdn(oid::COMMON_NAME, common_name));
+    der::tlv(der::SEQUENCE, &rdns.concat())
+}
+
+pub(crate) fn extension(id: &str, critical: bool, value: &[u8]) -> Vec<u8> {
+    if critical {
+        der::sequence(&[&der::oid(id), &der::boolean(true), &der::octet_string(value)])
+    } else {
+        der::sequence(&[&der::oid(id), &der::octet_string(value)])
+    }
+}
+
+pub(crate) struct TbsCertificate<'a> {
+    pub serial: &'a [u8],
+    pub issuer: &'a [u8],
+    pub subject: &'a [u8],
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub spki: &'a [u8],
+    pub extensions: Vec<Vec<u8>>,
+}
+
+impl TbsCertificate<'_> {
+    /// Sign with the CA key (ecdsa-with-SHA256)
+    pub fn sign(&self, key: &EcdsaKeyPair, rng: &SystemRandom) -> Result<Vec<u8>, PkiError> {
+        let algorithm = der::algorithm(oid::ECDSA_SHA256);
+        let validity = der::sequence(&[&der::time(self.not_before), &der::time(self.not_after)]);
+        let extensions = der::explicit(3, &der::tlv(der::SEQUENCE, &self.extensions.concat()));
+        let tbs = der::sequence(&[
+            &der::explicit(0, &der::integer(2)),
+            &der::unsigned(self.serial),
+            &algorithm,
+            self.issuer,
+            &validity,
+            self.subject,
+            self.spki,
+            &extensions,
+        ]);
+        sign(&tbs, key, rng)
+    }
+}
+
+/// Wrap signed content (TBSCertificate or TBSCertList) with its signature
+pub(crate) fn sign(tbs: &[u8], key: &EcdsaKeyPair, rng: &SystemRandom) -> Result<Vec<u8>, PkiError> {
+    let sig = key.sign(rng, tbs).map_err(|_| PkiError::Internal("signing failed".to_string()))?;
+    Ok(der::sequence(&[tbs, &der::algorithm(oid::ECDSA_SHA256), &der::bit_string(sig.as_ref())]))
+}
+
+/// Key usage bits (RFC 5280 4.2.1.3)
+pub(crate) mod key_usage {
+    pub const DIGITAL_SIGNATURE: u8 = 0;
+    pub const KEY_ENCIPHERMENT: u8 = 2;
+    pub const KEY_CERT_SIGN: u8 = 5;
+    pub const CRL_SIGN: u8 = 6;
+}
+
+/// `cRLDistributionPoints` with one full-name URI
+pub(crate) fn crl_distribution_point(url: &str) -> Vec<u8> {
+    let uri = der::implicit(6, url.as_bytes());
+    der::sequence(&[&der::sequence(&[&der::explicit(0, &der::explicit(0, &uri))])])
+}
+
+/// `authorityKeyIdentifier` with a key id only
+pub(crate) fn authority_key_id(key_id: &[u8]) -> Vec<u8> {
+    der::sequence(&[&der::implicit(0, key_id)])
+}
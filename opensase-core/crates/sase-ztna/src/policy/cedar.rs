@@ -0,0 +1,620 @@
+//! Cedar Policy Frontend
+//!
+//! A subset of [Cedar](https://www.cedarpolicy.com) compiled into the same
+//! evaluation tree as the YAML language, so both share type checking,
+//! deny-overrides and simulation:
+//!
+//! ```text
+//! @id("noncompliant-confidential")
+//! forbid (principal, action, resource)
+//! when { context.device.compliant == false && resource.sensitivity >= "Confidential" };
+//!
+//! @id("engineering-prod")
+//! @obligations("RequireMfa")
+//! permit (principal in Group::"engineering", action in [Action::"Read", Action::"Write"], resource)
+//! when { resource.tags["env"] == "prod" }
+//! unless { context.country in ["KP", "IR"] };
+//! ```
+//!
+//! `principal.*` reads identity attributes, `context.device.*` the device,
+//! `resource.*` the resource and the rest of `context.*` the request
+//! context; names below the root are those of the YAML language. Supported
+//! expressions are `== != < <= > >=`, `in [..]`, `has`, `like`,
+//! `.contains()`, `.containsAny()`, `.containsAll()`, `.isInRange(ip(..))`
+//! and `&&`, `||`, `!`. Scope constraints take `User`, `Group`, `Role`,
+//! `Action` and `Resource` entities.
+//!
+//! Annotations fill the fields Cedar has no syntax for: `@id`, `@name`,
+//! `@description`, `@priority`, `@enabled`, `@obligations` (YAML list or
+//! comma-separated access conditions) and `@effect` (`challenge` or `audit`).
+//! Unlike Cedar proper, a document with no matching `permit` does not deny
+//! by itself; as with YAML documents the rest of the engine decides.
+
+use super::lang::{Expr, PolicyDocument, PolicyLangError, PolicySource};
+use super::PolicyEffect;
+use crate::AccessCondition;
+
+/// Parse a Cedar document into a policy document ready for compilation
+pub fn parse(source: &str) -> Result<PolicyDocument, PolicyLangError> {
+    let mut parser = Parser { tokens: lex(source)?, pos: 0 };
+    let mut policies = Vec::new();
+    while !parser.at_end() {
+        policies.push(parser.policy(policies.len())?);
+    }
+    Ok(PolicyDocument { default: None, policies })
+}
+
+// =============================================================================
+// Lexer
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Punct(&'static str),
+}
+
+#[derive(Debug)]
+struct Token {
+    tok: Tok,
+    line: usize,
+}
+
+const PUNCTS: &[&str] = &[
+    "::", "==", "!=", "<=", ">=", "&&", "||",
+    "@", "(", ")", "{", "}", "[", "]", ",", ";", ".", "<", ">", "!",
+];
+
+fn lex(source: &str) -> Result<Vec<Token>, PolicyLangError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        if c == '\n' {
+            line += 1;
+            chars.next();
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if source[i..].starts_with("//") {
+            while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(syntax(line, "unterminated string")),
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, escaped)) => value.push(escaped),
+                        None => return Err(syntax(line, "unterminated string")),
+                    },
+                    Some((_, '\n')) => return Err(syntax(line, "unterminated string")),
+                    Some((_, c)) => value.push(c),
+                }
+            }
+            tokens.push(Token { tok: Tok::Str(value), line });
+        } else if c.is_ascii_digit() || (c == '-' && source[i + 1..].starts_with(|d: char| d.is_ascii_digit())) {
+            let mut number = String::new();
+            number.push(c);
+            chars.next();
+            while let Some((_, d)) = chars.next_if(|(_, d)| d.is_ascii_digit() || *d == '.') {
+                number.push(d);
+            }
+            tokens.push(Token { tok: Tok::Num(number), line });
+        } else if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some((_, d)) = chars.next_if(|(_, d)| d.is_alphanumeric() || *d == '_') {
+                ident.push(d);
+            }
+            tokens.push(Token { tok: Tok::Ident(ident), line });
+        } else {
+            let punct = PUNCTS.iter()
+                .copied()
+                .find(|p| source[i..].starts_with(*p))
+                .ok_or_else(|| syntax(line, &format!("unexpected '{}'", c)))?;
+            for _ in 0..punct.len() {
+                chars.next();
+            }
+            tokens.push(Token { tok: Tok::Punct(punct), line });
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn syntax(line: usize, message: &str) -> PolicyLangError {
+    PolicyLangError::Syntax(format!("line {}: {}", line, message))
+}
+
+// =============================================================================
+// Parser
+// =============================================================================
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+/// `Type::"id"`, keeping only the last path element of the type
+struct Entity {
+    kind: String,
+    id: String,
+}
+
+impl Parser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Tok> {
+        self.tokens.get(self.pos + offset).map(|t| &t.tok)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|t| t.line)
+            .unwrap_or(1)
+    }
+
+    fn error(&self, message: &str) -> PolicyLangError {
+        syntax(self.line(), message)
+    }
+
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(Tok::Ident(s)) | Some(Tok::Num(s)) => format!("'{}'", s),
+            Some(Tok::Str(s)) => format!("\"{}\"", s),
+            Some(Tok::Punct(p)) => format!("'{}'", p),
+            None => "end of input".to_string(),
+        }
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Punct(p)) if *p == punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Ident(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), PolicyLangError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}', found {}", punct, self.found())))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, PolicyLangError> {
+        match self.peek() {
+            Some(Tok::Ident(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.error(&format!("expected a name, found {}", self.found()))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, PolicyLangError> {
+        match self.peek() {
+            Some(Tok::Str(value)) => {
+                let value = value.clone();
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => Err(self.error(&format!("expected a string, found {}", self.found()))),
+        }
+    }
+
+    fn policy(&mut self, index: usize) -> Result<PolicySource, PolicyLangError> {
+        let mut annotations: Vec<(String, String, usize)> = Vec::new();
+        while self.eat("@") {
+            let line = self.line();
+            let key = self.ident()?;
+            self.expect("(")?;
+            let value = self.string()?;
+            self.expect(")")?;
+            if annotations.iter().any(|(k, ..)| *k == key) {
+                return Err(syntax(line, &format!("duplicate annotation @{}", key)));
+            }
+            annotations.push((key, value, line));
+        }
+
+        let effect = match self.ident()?.as_str() {
+            "permit" => PolicyEffect::Allow,
+            "forbid" => PolicyEffect::Deny,
+            other => return Err(self.error(&format!("expected permit or forbid, found '{}'", other))),
+        };
+
+        let mut clauses = Vec::new();
+        self.expect("(")?;
+        self.scope("principal", &mut clauses)?;
+        self.expect(",")?;
+        self.scope("action", &mut clauses)?;
+        self.expect(",")?;
+        self.scope("resource", &mut clauses)?;
+        self.expect(")")?;
+
+        loop {
+            let negate = if self.eat_ident("when") {
+                false
+            } else if self.eat_ident("unless") {
+                true
+            } else {
+                break;
+            };
+            self.expect("{")?;
+            let condition = self.expr()?;
+            self.expect("}")?;
+            clauses.push(if negate { Expr::Not { not: Box::new(condition) } } else { condition });
+        }
+        self.expect(";")?;
+
+        let mut source = PolicySource {
+            id: format!("policy{}", index),
+            name: None,
+            description: String::new(),
+            effect,
+            priority: 0,
+            enabled: true,
+            when: match clauses.len() {
+                0 => Expr::Const(true),
+                1 => clauses.remove(0),
+                _ => Expr::All { all: clauses },
+            },
+            obligations: Vec::new(),
+        };
+
+        for (key, value, line) in annotations {
+            let invalid = |what: &str| syntax(line, &format!("@{} {}, found \"{}\"", key, what, value));
+            match key.as_str() {
+                "id" => source.id = value,
+                "name" => source.name = Some(value),
+                "description" => source.description = value,
+                "priority" => source.priority = value.trim().parse().map_err(|_| invalid("must be an integer"))?,
+                "enabled" => source.enabled = value.trim().parse().map_err(|_| invalid("must be true or false"))?,
+                "effect" => {
+                    source.effect = match value.trim().to_ascii_lowercase().as_str() {
+                        "challenge" => PolicyEffect::Challenge,
+                        "audit" => PolicyEffect::Audit,
+                        _ => return Err(invalid("must be challenge or audit")),
+                    }
+                }
+                "obligations" => {
+                    source.obligations = obligations(&value).map_err(|e| syntax(line, &e))?;
+                }
+                _ => return Err(syntax(line, &format!("unknown annotation @{}", key))),
+            }
+        }
+
+        Ok(source)
+    }
+
+    /// One of the `(principal, action, resource)` scope constraints
+    fn scope(&mut self, variable: &str, clauses: &mut Vec<Expr>) -> Result<(), PolicyLangError> {
+        if !self.eat_ident(variable) {
+            return Err(self.error(&format!("expected '{}', found {}", variable, self.found())));
+        }
+        if self.eat("==") {
+            clauses.push(self.entity_constraint(variable, "==")?);
+        } else if self.eat_ident("in") {
+            clauses.push(self.entity_constraint(variable, "in")?);
+        }
+        Ok(())
+    }
+
+    fn entity(&mut self) -> Result<Entity, PolicyLangError> {
+        let mut kind = self.ident()?;
+        loop {
+            self.expect("::")?;
+            if let Some(Tok::Str(_)) = self.peek() {
+                return Ok(Entity { kind, id: self.string()? });
+            }
+            kind = self.ident()?;
+        }
+    }
+
+    /// `principal == User::"x"`, `principal in Group::"g"`,
+    /// `action in [Action::"Read", ..]` and the like
+    fn entity_constraint(&mut self, variable: &str, op: &str) -> Result<Expr, PolicyLangError> {
+        let line = self.line();
+
+        if variable == "action" && op == "in" && self.eat("[") {
+            let mut actions = Vec::new();
+            if !self.eat("]") {
+                loop {
+                    let entity = self.entity()?;
+                    if entity.kind != "Action" {
+                        return Err(syntax(line, &format!("expected Action entities, found {}", entity.kind)));
+                    }
+                    actions.push(quote(&entity.id));
+                    if self.eat("]") {
+                        break;
+                    }
+                    self.expect(",")?;
+                }
+            }
+            return Ok(Expr::Clause(format!("action in [{}]", actions.join(", "))));
+        }
+
+        let entity = self.entity()?;
+        let id = quote(&entity.id);
+        let clause = match (variable, op, entity.kind.as_str()) {
+            ("principal", "==", "User") => format!("identity.user_id == {}", id),
+            ("principal", "in", "Group") => format!("identity.groups contains {}", id),
+            ("principal", "in", "Role") => format!("identity.roles contains {}", id),
+            ("action", "==" | "in", "Action") => format!("action == {}", id),
+            ("resource", "==" | "in", "Resource") => format!("resource.id == {}", id),
+            _ => {
+                return Err(syntax(line, &format!(
+                    "unsupported constraint {} {} {}::\"{}\"",
+                    variable, op, entity.kind, entity.id
+                )))
+            }
+        };
+        Ok(Expr::Clause(clause))
+    }
+
+    fn expr(&mut self) -> Result<Expr, PolicyLangError> {
+        let mut terms = vec![self.conjunction()?];
+        while self.eat("||") {
+            terms.push(self.conjunction()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Any { any: terms } })
+    }
+
+    fn conjunction(&mut self) -> Result<Expr, PolicyLangError> {
+        let mut terms = vec![self.unary()?];
+        while self.eat("&&") {
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::All { all: terms } })
+    }
+
+    fn unary(&mut self) -> Result<Expr, PolicyLangError> {
+        if self.eat("!") {
+            return Ok(Expr::Not { not: Box::new(self.unary()?) });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, PolicyLangError> {
+        if self.eat("(") {
+            let inner = self.expr()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        if self.eat_ident("true") {
+            return Ok(Expr::Const(true));
+        }
+        if self.eat_ident("false") {
+            return Ok(Expr::Const(false));
+        }
+
+        let line = self.line();
+        let (segments, method) = self.path()?;
+
+        // Entity comparisons on the bare scope variables
+        if let [variable] = segments.as_slice() {
+            if matches!(variable.as_str(), "principal" | "action" | "resource") {
+                let op = if self.eat("==") {
+                    "=="
+                } else if self.eat_ident("in") {
+                    "in"
+                } else {
+                    return Err(syntax(line, &format!("expected == or in after {}", variable)));
+                };
+                return self.entity_constraint(variable, op);
+            }
+        }
+
+        let attr = attribute(&segments).map_err(|e| syntax(line, &e))?;
+
+        if let Some(method) = method {
+            self.expect("(")?;
+            let expr = match method.as_str() {
+                "contains" => Expr::Clause(format!("{} contains {}", attr, self.literal()?)),
+                "containsAny" => Expr::Clause(format!("{} contains {}", attr, self.literal_list()?.text())),
+                "containsAll" => {
+                    let items = self.literal_list()?;
+                    Expr::All {
+                        all: items.0.iter().map(|item| Expr::Clause(format!("{} contains {}", attr, item))).collect(),
+                    }
+                }
+                "isInRange" => {
+                    if !self.eat_ident("ip") {
+                        return Err(self.error("isInRange expects ip(\"<cidr>\")"));
+                    }
+                    self.expect("(")?;
+                    let cidr = self.string()?;
+                    self.expect(")")?;
+                    Expr::Clause(format!("{} in_cidr {}", attr, quote(&cidr)))
+                }
+                other => return Err(syntax(line, &format!("unsupported method {}()", other))),
+            };
+            self.expect(")")?;
+            return Ok(expr);
+        }
+
+        for op in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat(op) {
+                return Ok(Expr::Clause(format!("{} {} {}", attr, op, self.literal()?)));
+            }
+        }
+        if self.eat_ident("in") {
+            return Ok(Expr::Clause(format!("{} in {}", attr, self.literal_list()?.text())));
+        }
+        if self.eat_ident("has") {
+            let key = match self.peek() {
+                Some(Tok::Str(_)) => self.string()?,
+                _ => self.ident()?,
+            };
+            let key = attribute_key(&key).map_err(|e| syntax(line, &e))?;
+            return Ok(Expr::Clause(format!("{}.{} exists", attr, key)));
+        }
+        if self.eat_ident("like") {
+            let pattern = self.string()?;
+            return Ok(Expr::Clause(format!("{} matches {}", attr, quote(&glob_to_regex(&pattern)))));
+        }
+
+        // A bare boolean attribute
+        Ok(Expr::Clause(format!("{} == true", attr)))
+    }
+
+    /// Dotted attribute path, with `["key"]` segments, and a trailing
+    /// method name when followed by `(`
+    fn path(&mut self) -> Result<(Vec<String>, Option<String>), PolicyLangError> {
+        let mut segments = vec![self.ident()?];
+        loop {
+            if self.eat("[") {
+                segments.push(self.string()?);
+                self.expect("]")?;
+            } else if matches!(self.peek(), Some(Tok::Punct("."))) {
+                self.pos += 1;
+                let name = self.ident()?;
+                if matches!(self.peek(), Some(Tok::Punct("("))) {
+                    return Ok((segments, Some(name)));
+                }
+                segments.push(name);
+            } else {
+                return Ok((segments, None));
+            }
+        }
+    }
+
+    /// A string, number or boolean, rendered as a clause value
+    fn literal(&mut self) -> Result<String, PolicyLangError> {
+        match self.peek().cloned() {
+            Some(Tok::Str(value)) => {
+                self.pos += 1;
+                Ok(quote(&value))
+            }
+            Some(Tok::Num(number)) => {
+                self.pos += 1;
+                Ok(number)
+            }
+            Some(Tok::Ident(word)) if word == "true" || word == "false" => {
+                self.pos += 1;
+                Ok(word)
+            }
+            Some(Tok::Ident(_)) if matches!(self.peek_at(1), Some(Tok::Punct("::"))) => {
+                let entity = self.entity()?;
+                Ok(quote(&entity.id))
+            }
+            _ => Err(self.error(&format!("expected a value, found {}", self.found()))),
+        }
+    }
+
+    fn literal_list(&mut self) -> Result<Literals, PolicyLangError> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        if self.eat("]") {
+            return Ok(Literals(items));
+        }
+        loop {
+            items.push(self.literal()?);
+            if self.eat("]") {
+                return Ok(Literals(items));
+            }
+            self.expect(",")?;
+        }
+    }
+}
+
+/// Rendered list items
+struct Literals(Vec<String>);
+
+impl Literals {
+    fn text(&self) -> String {
+        format!("[{}]", self.0.join(", "))
+    }
+}
+
+/// Map a Cedar attribute path onto the policy language's attribute names
+fn attribute(segments: &[String]) -> Result<String, String> {
+    let (root, rest) = segments.split_first().ok_or("expected an attribute")?;
+    let rest = rest.iter().map(|s| attribute_key(s)).collect::<Result<Vec<_>, _>>()?.join(".");
+    if rest.is_empty() {
+        return match root.as_str() {
+            "action" => Ok("action".to_string()),
+            "context" | "principal" | "resource" => Err(format!("{} needs an attribute", root)),
+            other => Err(format!("unknown variable '{}'", other)),
+        };
+    }
+    match root.as_str() {
+        "principal" => Ok(format!("identity.{}", rest)),
+        "resource" => Ok(format!("resource.{}", rest)),
+        "context" => Ok(match rest.strip_prefix("device.") {
+            Some(device) => format!("device.{}", device),
+            None => format!("context.{}", rest),
+        }),
+        "action" => Err("action has no attributes".to_string()),
+        other => Err(format!("unknown variable '{}'", other)),
+    }
+}
+
+/// Tag and attribute keys have to survive as a single clause word
+fn attribute_key(key: &str) -> Result<&str, String> {
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '/')) {
+        Ok(key)
+    } else {
+        Err(format!("unsupported attribute key \"{}\"", key))
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Cedar `like` pattern: `*` matches anything, `\*` a literal star
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// `@obligations` value: a YAML list, one condition, or a comma-separated
+/// list of unit conditions
+fn obligations(value: &str) -> Result<Vec<AccessCondition>, String> {
+    serde_yaml::from_str::<Vec<AccessCondition>>(value)
+        .or_else(|_| serde_yaml::from_str::<AccessCondition>(value).map(|c| vec![c]))
+        .or_else(|_| {
+            value.split(',')
+                .map(|v| serde_yaml::from_str::<AccessCondition>(v.trim()))
+                .collect()
+        })
+        .map_err(|e| format!("invalid @obligations: {}", e))
+}
@@ -0,0 +1,1440 @@
+//! ABAC Policy Language
+//!
+//! Declarative attribute-based policies written in YAML and compiled into an
+//! evaluation tree. Each policy has an effect and a `when` expression built
+//! from `all` / `any` / `not` blocks and single-line clauses of the form
+//! `<attribute> <operator> <value>`:
+//!
+//! ```yaml
+//! default: deny
+//! policies:
+//!   - id: contractors-off-prod
+//!     effect: deny
+//!     when:
+//!       all:
+//!         - identity.groups contains contractors
+//!         - resource.tags.env == prod
+//!   - id: engineering-on-managed
+//!     effect: allow
+//!     when:
+//!       all:
+//!         - identity.groups contains [engineering, sre]
+//!         - device.managed == true
+//!         - device.trust_level >= Medium
+//!         - context.hour >= 7
+//!         - not: context.country in [KP, IR]
+//!     obligations:
+//!       - RequireMfa
+//! ```
+//!
+//! Attributes and literals are type-checked when the document is compiled,
+//! so a typo fails the load instead of silently never matching. Conflicts
+//! resolve deny-overrides: any matching `deny` wins, then `challenge`, then
+//! `allow`; `audit` policies are reported but never change the outcome.
+//! Attributes absent from a request (no geo fix, untagged resource) fail
+//! every comparison except `exists`.
+//!
+//! The same policies can be written in the Cedar subset parsed by
+//! [`cedar`](super::cedar); both compile to the tree built here.
+
+use super::PolicyEffect;
+use crate::{
+    AccessAction, AccessCondition, AccessRequest, DeviceType, IdentityProvider, NetworkType,
+    ResourceType, RiskSignalType,
+};
+use chrono::{Datelike, Timelike, Weekday};
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+// Canonical names for enumerated attributes. Request values are mapped onto
+// these by exhaustive matches below, so a new enum variant fails to compile
+// rather than indexing past the end of a table.
+const PROVIDERS: &[&str] = &["Local", "Saml", "Oidc", "Ldap", "Azure", "Okta", "Google"];
+const DEVICE_TYPES: &[&str] = &["Desktop", "Laptop", "Mobile", "Tablet", "Server", "IoT", "Unknown"];
+const TRUST_LEVELS: &[&str] = &["Untrusted", "Low", "Medium", "High", "Full"];
+const RESOURCE_TYPES: &[&str] = &[
+    "Application", "Api", "Database", "FileShare", "Network", "Service", "Infrastructure",
+];
+const SENSITIVITIES: &[&str] = &["Public", "Internal", "Confidential", "Restricted", "TopSecret"];
+const ACTIONS: &[&str] = &["Read", "Write", "Execute", "Delete", "Admin", "Connect"];
+const NETWORKS: &[&str] = &["Corporate", "VPN", "Home", "PublicWifi", "Mobile", "Unknown"];
+const WEEKDAYS: &[&str] = &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const SIGNALS: &[&str] = &[
+    "ImpossibleTravel", "NewDevice", "NewLocation", "UnusualTime", "UnusualBehavior",
+    "CompromisedCredential", "MalwareDetected", "PrivilegeEscalation", "DataExfiltration",
+    "BruteForceAttempt",
+];
+
+fn device_type_name(t: DeviceType) -> &'static str {
+    match t {
+        DeviceType::Desktop => DEVICE_TYPES[0],
+        DeviceType::Laptop => DEVICE_TYPES[1],
+        DeviceType::Mobile => DEVICE_TYPES[2],
+        DeviceType::Tablet => DEVICE_TYPES[3],
+        DeviceType::Server => DEVICE_TYPES[4],
+        DeviceType::IoT => DEVICE_TYPES[5],
+        DeviceType::Unknown => DEVICE_TYPES[6],
+    }
+}
+
+fn resource_type_name(t: ResourceType) -> &'static str {
+    match t {
+        ResourceType::Application => RESOURCE_TYPES[0],
+        ResourceType::Api => RESOURCE_TYPES[1],
+        ResourceType::Database => RESOURCE_TYPES[2],
+        ResourceType::FileShare => RESOURCE_TYPES[3],
+        ResourceType::Network => RESOURCE_TYPES[4],
+        ResourceType::Service => RESOURCE_TYPES[5],
+        ResourceType::Infrastructure => RESOURCE_TYPES[6],
+    }
+}
+
+fn action_name(a: AccessAction) -> &'static str {
+    match a {
+        AccessAction::Read => ACTIONS[0],
+        AccessAction::Write => ACTIONS[1],
+        AccessAction::Execute => ACTIONS[2],
+        AccessAction::Delete => ACTIONS[3],
+        AccessAction::Admin => ACTIONS[4],
+        AccessAction::Connect => ACTIONS[5],
+    }
+}
+
+fn network_name(n: NetworkType) -> &'static str {
+    match n {
+        NetworkType::Corporate => NETWORKS[0],
+        NetworkType::VPN => NETWORKS[1],
+        NetworkType::Home => NETWORKS[2],
+        NetworkType::PublicWifi => NETWORKS[3],
+        NetworkType::Mobile => NETWORKS[4],
+        NetworkType::Unknown => NETWORKS[5],
+    }
+}
+
+fn weekday_name(d: Weekday) -> &'static str {
+    match d {
+        Weekday::Mon => WEEKDAYS[0],
+        Weekday::Tue => WEEKDAYS[1],
+        Weekday::Wed => WEEKDAYS[2],
+        Weekday::Thu => WEEKDAYS[3],
+        Weekday::Fri => WEEKDAYS[4],
+        Weekday::Sat => WEEKDAYS[5],
+        Weekday::Sun => WEEKDAYS[6],
+    }
+}
+
+fn signal_name(s: RiskSignalType) -> &'static str {
+    match s {
+        RiskSignalType::ImpossibleTravel => SIGNALS[0],
+        RiskSignalType::NewDevice => SIGNALS[1],
+        RiskSignalType::NewLocation => SIGNALS[2],
+        RiskSignalType::UnusualTime => SIGNALS[3],
+        RiskSignalType::UnusualBehavior => SIGNALS[4],
+        RiskSignalType::CompromisedCredential => SIGNALS[5],
+        RiskSignalType::MalwareDetected => SIGNALS[6],
+        RiskSignalType::PrivilegeEscalation => SIGNALS[7],
+        RiskSignalType::DataExfiltration => SIGNALS[8],
+        RiskSignalType::BruteForceAttempt => SIGNALS[9],
+    }
+}
+
+// =============================================================================
+// Source Document
+// =============================================================================
+
+/// Policy document as written
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDocument {
+    /// Outcome when no policy matches (`allow` or `deny`); unset leaves the
+    /// decision to the rest of the engine
+    #[serde(default)]
+    pub default: Option<PolicyEffect>,
+    pub policies: Vec<PolicySource>,
+}
+
+impl PolicyDocument {
+    /// Parse a document written in either policy language
+    pub fn parse(source: &str, format: PolicyFormat) -> Result<Self, PolicyLangError> {
+        match format {
+            PolicyFormat::Yaml => serde_yaml::from_str(source).map_err(|e| PolicyLangError::Syntax(e.to_string())),
+            PolicyFormat::Cedar => super::cedar::parse(source),
+        }
+    }
+}
+
+/// Language a policy document is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyFormat {
+    Yaml,
+    /// The Cedar subset in [`cedar`](super::cedar)
+    Cedar,
+}
+
+impl PolicyFormat {
+    /// Format for a file name: `.yaml` / `.yml` or `.cedar`
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "cedar" => Some(Self::Cedar),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicySource {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    pub effect: PolicyEffect,
+    /// Ordering within the set; only affects trace and reason order, never
+    /// the outcome
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Match condition; omitted means the policy always matches
+    #[serde(default = "always")]
+    pub when: Expr,
+    /// Access conditions attached when the policy allows
+    #[serde(default)]
+    pub obligations: Vec<AccessCondition>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn always() -> Expr {
+    Expr::Const(true)
+}
+
+/// Match expression as written
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Expr {
+    Const(bool),
+    Clause(String),
+    All { all: Vec<Expr> },
+    Any { any: Vec<Expr> },
+    Not { not: Box<Expr> },
+}
+
+#[derive(Debug, Clone)]
+pub enum PolicyLangError {
+    /// Document is not valid YAML or does not match the schema
+    Syntax(String),
+    DuplicateId(String),
+    /// `default` must be `allow` or `deny`
+    InvalidDefault(PolicyEffect),
+    /// A policy failed to compile
+    Policy { policy: String, message: String },
+}
+
+impl std::fmt::Display for PolicyLangError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(e) => write!(f, "Policy document invalid: {}", e),
+            Self::DuplicateId(id) => write!(f, "Duplicate policy id: {}", id),
+            Self::InvalidDefault(effect) => write!(f, "Default effect must be allow or deny, not {:?}", effect),
+            Self::Policy { policy, message } => write!(f, "Policy {}: {}", policy, message),
+        }
+    }
+}
+
+impl std::error::Error for PolicyLangError {}
+
+// =============================================================================
+// Compiled Policy Set
+// =============================================================================
+
+/// Compiled, immutable policy set
+#[derive(Debug)]
+pub struct PolicySet {
+    /// Enabled policies by priority (higher first), then id
+    policies: Vec<CompiledPolicy>,
+    default: Option<PolicyEffect>,
+}
+
+#[derive(Debug)]
+pub struct CompiledPolicy {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub effect: PolicyEffect,
+    pub priority: i32,
+    pub obligations: Vec<AccessCondition>,
+    root: Node,
+}
+
+/// Result of evaluating a request against a policy set
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub enum Outcome {
+    Allow,
+    Deny,
+    Challenge,
+    /// No policy matched and the set has no default
+    NotApplicable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AbacDecision {
+    pub outcome: Outcome,
+    /// Every matching policy, in evaluation order
+    pub matched: Vec<String>,
+    /// Policies whose effect produced the outcome
+    pub determining: Vec<String>,
+    /// Matching audit policies
+    pub audited: Vec<String>,
+    /// Access conditions from the determining policies (allow only)
+    pub obligations: Vec<AccessCondition>,
+    pub reasons: Vec<String>,
+}
+
+/// Decision plus a clause-by-clause trace of every policy
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    pub decision: AbacDecision,
+    pub trace: Vec<PolicyTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyTrace {
+    pub policy_id: String,
+    pub effect: PolicyEffect,
+    pub matched: bool,
+    /// Clauses actually evaluated; `all` / `any` short-circuit
+    pub clauses: Vec<ClauseTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClauseTrace {
+    pub clause: String,
+    /// Attribute value seen in the request
+    pub observed: String,
+    pub result: bool,
+}
+
+/// One request replayed against a candidate policy document
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunResult {
+    pub request_id: String,
+    /// Outcome under the installed policy set
+    pub current: Outcome,
+    /// Outcome and trace under the candidate
+    pub simulation: Simulation,
+    pub changed: bool,
+}
+
+impl PolicySet {
+    /// Parse and compile a YAML policy document
+    pub fn parse(source: &str) -> Result<Self, PolicyLangError> {
+        Self::parse_as(source, PolicyFormat::Yaml)
+    }
+
+    /// Parse and compile a document in the given language
+    pub fn parse_as(source: &str, format: PolicyFormat) -> Result<Self, PolicyLangError> {
+        Self::compile(PolicyDocument::parse(source, format)?)
+    }
+
+    pub fn compile(document: PolicyDocument) -> Result<Self, PolicyLangError> {
+        if let Some(effect) = document.default {
+            if !matches!(effect, PolicyEffect::Allow | PolicyEffect::Deny) {
+                return Err(PolicyLangError::InvalidDefault(effect));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut policies = Vec::with_capacity(document.policies.len());
+        for source in document.policies {
+            if source.id.trim().is_empty() {
+                return Err(PolicyLangError::Policy {
+                    policy: String::new(),
+                    message: "id must not be empty".to_string(),
+                });
+            }
+            if !seen.insert(source.id.clone()) {
+                return Err(PolicyLangError::DuplicateId(source.id));
+            }
+
+            // Disabled policies are still compiled so mistakes surface on load
+            let root = compile_expr(&source.when).map_err(|message| PolicyLangError::Policy {
+                policy: source.id.clone(),
+                message,
+            })?;
+            if !source.enabled {
+                continue;
+            }
+
+            policies.push(CompiledPolicy {
+                name: source.name.unwrap_or_else(|| source.id.clone()),
+                id: source.id,
+                description: source.description,
+                effect: source.effect,
+                priority: source.priority,
+                obligations: source.obligations,
+                root,
+            });
+        }
+
+        policies.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(Self { policies, default: document.default })
+    }
+
+    pub fn policies(&self) -> &[CompiledPolicy] {
+        &self.policies
+    }
+
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Evaluate a request
+    pub fn evaluate(&self, request: &AccessRequest) -> AbacDecision {
+        let matched = self.matching(request, None);
+        self.resolve(&matched)
+    }
+
+    /// Evaluate a request and record how every policy reached its result
+    pub fn simulate(&self, request: &AccessRequest) -> Simulation {
+        let mut trace = Vec::with_capacity(self.policies.len());
+        let matched = self.matching(request, Some(&mut trace));
+        Simulation { decision: self.resolve(&matched), trace }
+    }
+
+    fn matching(&self, request: &AccessRequest, mut traces: Option<&mut Vec<PolicyTrace>>) -> Vec<&CompiledPolicy> {
+        let mut matched = Vec::new();
+        for policy in &self.policies {
+            let hit = match traces.as_deref_mut() {
+                Some(traces) => {
+                    let mut clauses = Vec::new();
+                    let hit = policy.root.eval(request, Some(&mut clauses));
+                    traces.push(PolicyTrace {
+                        policy_id: policy.id.clone(),
+                        effect: policy.effect,
+                        matched: hit,
+                        clauses,
+                    });
+                    hit
+                }
+                None => policy.root.eval(request, None),
+            };
+            if hit {
+                matched.push(policy);
+            }
+        }
+        matched
+    }
+
+    /// Deny-overrides: deny, then challenge, then allow, then the default
+    fn resolve(&self, matched: &[&CompiledPolicy]) -> AbacDecision {
+        let deny = with_effect(matched, PolicyEffect::Deny);
+        let challenge = with_effect(matched, PolicyEffect::Challenge);
+        let allow = with_effect(matched, PolicyEffect::Allow);
+
+        let (outcome, determining) = if !deny.is_empty() {
+            (Outcome::Deny, deny)
+        } else if !challenge.is_empty() {
+            (Outcome::Challenge, challenge)
+        } else if !allow.is_empty() {
+            (Outcome::Allow, allow)
+        } else {
+            let outcome = match self.default {
+                Some(PolicyEffect::Allow) => Outcome::Allow,
+                Some(PolicyEffect::Deny) => Outcome::Deny,
+                _ => Outcome::NotApplicable,
+            };
+            (outcome, Vec::new())
+        };
+
+        let reasons = if determining.is_empty() {
+            match outcome {
+                Outcome::Allow => vec!["No ABAC policy matched; default allow".to_string()],
+                Outcome::Deny => vec!["No ABAC policy matched; default deny".to_string()],
+                _ => Vec::new(),
+            }
+        } else {
+            let verb = match outcome {
+                Outcome::Deny => "Denied",
+                Outcome::Challenge => "Challenge required",
+                _ => "Allowed",
+            };
+            determining.iter().map(|p| format!("{} by ABAC policy: {}", verb, p.name)).collect()
+        };
+
+        let obligations = if outcome == Outcome::Allow {
+            determining.iter().flat_map(|p| p.obligations.iter().cloned()).collect()
+        } else {
+            Vec::new()
+        };
+
+        AbacDecision {
+            outcome,
+            matched: matched.iter().map(|p| p.id.clone()).collect(),
+            determining: determining.iter().map(|p| p.id.clone()).collect(),
+            audited: with_effect(matched, PolicyEffect::Audit).iter().map(|p| p.id.clone()).collect(),
+            obligations,
+            reasons,
+        }
+    }
+}
+
+fn with_effect<'p>(matched: &[&'p CompiledPolicy], effect: PolicyEffect) -> Vec<&'p CompiledPolicy> {
+    matched.iter().filter(|p| p.effect == effect).copied().collect()
+}
+
+// =============================================================================
+// Evaluation Tree
+// =============================================================================
+
+#[derive(Debug)]
+enum Node {
+    Const(bool),
+    All(Vec<Node>),
+    Any(Vec<Node>),
+    Not(Box<Node>),
+    Test(Test),
+}
+
+impl Node {
+    fn eval(&self, request: &AccessRequest, mut trace: Option<&mut Vec<ClauseTrace>>) -> bool {
+        match self {
+            Node::Const(value) => *value,
+            Node::All(nodes) => nodes.iter().all(|n| n.eval(request, trace.as_deref_mut())),
+            Node::Any(nodes) => nodes.iter().any(|n| n.eval(request, trace.as_deref_mut())),
+            Node::Not(node) => !node.eval(request, trace),
+            Node::Test(test) => test.eval(request, trace),
+        }
+    }
+
+    /// Conjunction with nested `all` flattened and constants folded
+    fn all(children: Vec<Node>) -> Node {
+        let mut flat = Vec::with_capacity(children.len());
+        for child in children {
+            match child {
+                Node::Const(true) => {}
+                Node::Const(false) => return Node::Const(false),
+                Node::All(inner) => flat.extend(inner),
+                other => flat.push(other),
+            }
+        }
+        match flat.len() {
+            0 => Node::Const(true),
+            1 => flat.remove(0),
+            _ => Node::All(flat),
+        }
+    }
+
+    /// Disjunction with nested `any` flattened and constants folded
+    fn any(children: Vec<Node>) -> Node {
+        let mut flat = Vec::with_capacity(children.len());
+        for child in children {
+            match child {
+                Node::Const(false) => {}
+                Node::Const(true) => return Node::Const(true),
+                Node::Any(inner) => flat.extend(inner),
+                other => flat.push(other),
+            }
+        }
+        match flat.len() {
+            0 => Node::Const(false),
+            1 => flat.remove(0),
+            _ => Node::Any(flat),
+        }
+    }
+
+    fn not(inner: Node) -> Node {
+        match inner {
+            Node::Const(value) => Node::Const(!value),
+            Node::Not(inner) => *inner,
+            other => Node::Not(Box::new(other)),
+        }
+    }
+}
+
+fn compile_expr(expr: &Expr) -> Result<Node, String> {
+    Ok(match expr {
+        Expr::Const(value) => Node::Const(*value),
+        Expr::Clause(clause) => {
+            Node::Test(parse_clause(clause).map_err(|e| format!("`{}`: {}", clause.trim(), e))?)
+        }
+        Expr::All { all } => Node::all(all.iter().map(compile_expr).collect::<Result<_, _>>()?),
+        Expr::Any { any } => Node::any(any.iter().map(compile_expr).collect::<Result<_, _>>()?),
+        Expr::Not { not } => Node::not(compile_expr(not)?),
+    })
+}
+
+/// A single compiled clause
+#[derive(Debug)]
+struct Test {
+    source: String,
+    attr: Attr,
+    op: Op,
+    literal: Literal,
+}
+
+impl Test {
+    fn eval(&self, request: &AccessRequest, trace: Option<&mut Vec<ClauseTrace>>) -> bool {
+        let value = self.attr.value(request);
+        let result = match self.op {
+            Op::Exists => !matches!(value, Value::Missing),
+            op => compare(op, &value, &self.literal),
+        };
+
+        if let Some(trace) = trace {
+            trace.push(ClauseTrace {
+                clause: self.source.clone(),
+                observed: value.to_string(),
+                result,
+            });
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    Contains,
+    InCidr,
+    Matches,
+    StartsWith,
+    EndsWith,
+    Exists,
+}
+
+impl Op {
+    fn keyword(word: &str) -> Option<Self> {
+        Some(match word {
+            "in" => Op::In,
+            "contains" => Op::Contains,
+            "in_cidr" => Op::InCidr,
+            "matches" => Op::Matches,
+            "starts_with" => Op::StartsWith,
+            "ends_with" => Op::EndsWith,
+            "exists" => Op::Exists,
+            _ => return None,
+        })
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::In => "in",
+            Op::Contains => "contains",
+            Op::InCidr => "in_cidr",
+            Op::Matches => "matches",
+            Op::StartsWith => "starts_with",
+            Op::EndsWith => "ends_with",
+            Op::Exists => "exists",
+        }
+    }
+}
+
+/// Right-hand side of a clause, parsed for the attribute's type
+#[derive(Debug)]
+enum Literal {
+    None,
+    Str(String),
+    Strs(Vec<String>),
+    Num(f64),
+    Nums(Vec<f64>),
+    Bool(bool),
+    Ip(IpAddr),
+    Cidrs(Vec<IpNetwork>),
+    Regex(regex::Regex),
+}
+
+/// Attribute value extracted from a request
+enum Value<'a> {
+    Str(&'a str),
+    Num(f64),
+    Bool(bool),
+    List(Vec<&'a str>),
+    Ip(IpAddr),
+    Missing,
+}
+
+impl std::fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::List(items) => write!(f, "[{}]", items.join(", ")),
+            Value::Ip(ip) => write!(f, "{}", ip),
+            Value::Missing => write!(f, "<missing>"),
+        }
+    }
+}
+
+fn compare(op: Op, value: &Value, literal: &Literal) -> bool {
+    match (value, literal) {
+        (Value::Missing, _) => false,
+        (Value::Str(s), Literal::Str(l)) => match op {
+            Op::Eq => *s == l.as_str(),
+            Op::Ne => *s != l.as_str(),
+            Op::StartsWith => s.starts_with(l.as_str()),
+            Op::EndsWith => s.ends_with(l.as_str()),
+            _ => false,
+        },
+        (Value::Str(s), Literal::Strs(l)) => l.iter().any(|l| l.as_str() == *s),
+        (Value::Str(s), Literal::Regex(r)) => r.is_match(s),
+        (Value::Num(n), Literal::Num(l)) => match op {
+            Op::Eq => n == l,
+            Op::Ne => n != l,
+            Op::Lt => n < l,
+            Op::Le => n <= l,
+            Op::Gt => n > l,
+            Op::Ge => n >= l,
+            _ => false,
+        },
+        (Value::Num(n), Literal::Nums(l)) => l.contains(n),
+        (Value::Bool(b), Literal::Bool(l)) => match op {
+            Op::Eq => b == l,
+            Op::Ne => b != l,
+            _ => false,
+        },
+        (Value::List(items), Literal::Strs(l)) => {
+            items.iter().any(|item| l.iter().any(|l| l.as_str() == *item))
+        }
+        (Value::Ip(ip), Literal::Ip(l)) => match op {
+            Op::Eq => ip == l,
+            Op::Ne => ip != l,
+            _ => false,
+        },
+        (Value::Ip(ip), Literal::Cidrs(networks)) => networks.iter().any(|n| n.contains(*ip)),
+        _ => false,
+    }
+}
+
+// =============================================================================
+// Attributes
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Attr {
+    UserId,
+    Email,
+    Groups,
+    Roles,
+    IdentityAttribute(String),
+    MfaVerified,
+    Provider,
+    DeviceId,
+    DeviceType,
+    DeviceOs,
+    DeviceOsVersion,
+    DeviceManaged,
+    DeviceCompliant,
+    DeviceTrust,
+    Posture(PostureCheck),
+    ResourceId,
+    ResourceName,
+    ResourceType,
+    Sensitivity,
+    ResourceOwner,
+    ResourceTag(String),
+    Action,
+    ClientIp,
+    Country,
+    Region,
+    Network,
+    Hour,
+    Weekday,
+    RiskScore,
+    Signals,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PostureCheck {
+    Firewall,
+    Antivirus,
+    DiskEncrypted,
+    OsPatched,
+    ScreenLock,
+    Jailbroken,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Str,
+    Num,
+    Bool,
+    Ip,
+    /// One of a fixed set of names
+    Enum(&'static [&'static str]),
+    /// Named levels that also compare by rank
+    Ordinal(&'static [&'static str]),
+    /// Set of strings, optionally drawn from fixed names
+    List(Option<&'static [&'static str]>),
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Str => "string",
+            Kind::Num => "numeric",
+            Kind::Bool => "boolean",
+            Kind::Ip => "address",
+            Kind::Enum(_) => "enumerated",
+            Kind::Ordinal(_) => "ordinal",
+            Kind::List(_) => "list",
+        }
+    }
+}
+
+impl Attr {
+    fn resolve(path: &str) -> Option<Self> {
+        if let Some(key) = path.strip_prefix("identity.attributes.") {
+            return (!key.is_empty()).then(|| Attr::IdentityAttribute(key.to_string()));
+        }
+        if let Some(key) = path.strip_prefix("resource.tags.") {
+            return (!key.is_empty()).then(|| Attr::ResourceTag(key.to_string()));
+        }
+
+        Some(match path {
+            "identity.user_id" => Attr::UserId,
+            "identity.email" => Attr::Email,
+            "identity.groups" => Attr::Groups,
+            "identity.roles" => Attr::Roles,
+            "identity.mfa_verified" => Attr::MfaVerified,
+            "identity.provider" => Attr::Provider,
+            "device.id" => Attr::DeviceId,
+            "device.type" => Attr::DeviceType,
+            "device.os" => Attr::DeviceOs,
+            "device.os_version" => Attr::DeviceOsVersion,
+            "device.managed" => Attr::DeviceManaged,
+            "device.compliant" => Attr::DeviceCompliant,
+            "device.trust_level" => Attr::DeviceTrust,
+            "device.posture.firewall_enabled" => Attr::Posture(PostureCheck::Firewall),
+            "device.posture.antivirus_running" => Attr::Posture(PostureCheck::Antivirus),
+            "device.posture.disk_encrypted" => Attr::Posture(PostureCheck::DiskEncrypted),
+            "device.posture.os_patched" => Attr::Posture(PostureCheck::OsPatched),
+            "device.posture.screen_lock_enabled" => Attr::Posture(PostureCheck::ScreenLock),
+            "device.posture.jailbroken" => Attr::Posture(PostureCheck::Jailbroken),
+            "resource.id" => Attr::ResourceId,
+            "resource.name" => Attr::ResourceName,
+            "resource.type" => Attr::ResourceType,
+            "resource.sensitivity" => Attr::Sensitivity,
+            "resource.owner" => Attr::ResourceOwner,
+            "action" => Attr::Action,
+            "context.ip" => Attr::ClientIp,
+            "context.country" => Attr::Country,
+            "context.region" => Attr::Region,
+            "context.network" => Attr::Network,
+            "context.hour" => Attr::Hour,
+            "context.weekday" => Attr::Weekday,
+            "context.risk_score" => Attr::RiskScore,
+            "context.signals" => Attr::Signals,
+            _ => return None,
+        })
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Attr::UserId | Attr::Email | Attr::IdentityAttribute(_) | Attr::DeviceId
+            | Attr::DeviceOs | Attr::DeviceOsVersion | Attr::ResourceId | Attr::ResourceName
+            | Attr::ResourceOwner | Attr::ResourceTag(_) | Attr::Country | Attr::Region => Kind::Str,
+            Attr::Groups | Attr::Roles => Kind::List(None),
+            Attr::Signals => Kind::List(Some(SIGNALS)),
+            Attr::MfaVerified | Attr::DeviceManaged | Attr::DeviceCompliant | Attr::Posture(_) => Kind::Bool,
+            Attr::Provider => Kind::Enum(PROVIDERS),
+            Attr::DeviceType => Kind::Enum(DEVICE_TYPES),
+            Attr::ResourceType => Kind::Enum(RESOURCE_TYPES),
+            Attr::Action => Kind::Enum(ACTIONS),
+            Attr::Network => Kind::Enum(NETWORKS),
+            Attr::Weekday => Kind::Enum(WEEKDAYS),
+            Attr::DeviceTrust => Kind::Ordinal(TRUST_LEVELS),
+            Attr::Sensitivity => Kind::Ordinal(SENSITIVITIES),
+            Attr::Hour | Attr::RiskScore => Kind::Num,
+            Attr::ClientIp => Kind::Ip,
+        }
+    }
+
+    fn value<'a>(&self, request: &'a AccessRequest) -> Value<'a> {
+        let identity = &request.identity;
+        let device = &request.device;
+        let resource = &request.resource;
+        let context = &request.context;
+
+        match self {
+            Attr::UserId => Value::Str(&identity.user_id),
+            Attr::Email => Value::Str(&identity.email),
+            Attr::Groups => Value::List(identity.groups.iter().map(String::as_str).collect()),
+            Attr::Roles => Value::List(identity.roles.iter().map(String::as_str).collect()),
+            Attr::IdentityAttribute(key) => identity.attributes.get(key)
+                .map(|v| Value::Str(v))
+                .unwrap_or(Value::Missing),
+            Attr::MfaVerified => Value::Bool(identity.mfa_verified),
+            Attr::Provider => Value::Str(match identity.provider {
+                IdentityProvider::Local => PROVIDERS[0],
+                IdentityProvider::Saml { .. } => PROVIDERS[1],
+                IdentityProvider::Oidc { .. } => PROVIDERS[2],
+                IdentityProvider::Ldap { .. } => PROVIDERS[3],
+                IdentityProvider::Azure => PROVIDERS[4],
+                IdentityProvider::Okta => PROVIDERS[5],
+                IdentityProvider::Google => PROVIDERS[6],
+            }),
+            Attr::DeviceId => Value::Str(&device.id),
+            Attr::DeviceType => Value::Str(device_type_name(device.device_type)),
+            Attr::DeviceOs => Value::Str(&device.os),
+            Attr::DeviceOsVersion => Value::Str(&device.os_version),
+            Attr::DeviceManaged => Value::Bool(device.managed),
+            Attr::DeviceCompliant => Value::Bool(device.compliant),
+            Attr::DeviceTrust => Value::Num(device.trust_level as u8 as f64),
+            Attr::Posture(check) => Value::Bool(match check {
+                PostureCheck::Firewall => device.posture.firewall_enabled,
+                PostureCheck::Antivirus => device.posture.antivirus_running,
+                PostureCheck::DiskEncrypted => device.posture.disk_encrypted,
+                PostureCheck::OsPatched => device.posture.os_patched,
+                PostureCheck::ScreenLock => device.posture.screen_lock_enabled,
+                PostureCheck::Jailbroken => device.posture.jailbroken,
+            }),
+            Attr::ResourceId => Value::Str(&resource.id),
+            Attr::ResourceName => Value::Str(&resource.name),
+            Attr::ResourceType => Value::Str(resource_type_name(resource.resource_type)),
+            Attr::Sensitivity => Value::Num(resource.sensitivity as u8 as f64),
+            Attr::ResourceOwner => Value::Str(&resource.owner),
+            Attr::ResourceTag(key) => resource.tags.get(key)
+                .map(|v| Value::Str(v))
+                .unwrap_or(Value::Missing),
+            Attr::Action => Value::Str(action_name(request.action)),
+            Attr::ClientIp => Value::Ip(context.client_ip),
+            Attr::Country => context.geo_location.as_ref()
+                .map(|g| Value::Str(&g.country))
+                .unwrap_or(Value::Missing),
+            Attr::Region => context.geo_location.as_ref()
+                .and_then(|g| g.region.as_deref())
+                .map(Value::Str)
+                .unwrap_or(Value::Missing),
+            Attr::Network => Value::Str(network_name(context.network_type)),
+            Attr::Hour => Value::Num(context.time_of_access.hour() as f64),
+            Attr::Weekday => Value::Str(weekday_name(context.time_of_access.weekday())),
+            Attr::RiskScore => Value::Num(context.risk_score),
+            Attr::Signals => {
+                Value::List(context.signals.iter().map(|s| signal_name(s.signal_type)).collect())
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Clause Parser
+// =============================================================================
+
+#[derive(Debug)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    List(Vec<String>),
+    Op(Op),
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn parse_clause(source: &str) -> Result<Test, String> {
+    let mut tokens = tokenize(source)?.into_iter();
+
+    let path = match tokens.next() {
+        Some(Token::Word(word)) => word,
+        _ => return Err("expected an attribute".to_string()),
+    };
+    let attr = Attr::resolve(&path).ok_or_else(|| format!("unknown attribute '{}'", path))?;
+
+    let op = match tokens.next() {
+        Some(Token::Op(op)) => op,
+        Some(Token::Word(word)) => Op::keyword(&word).ok_or_else(|| format!("unknown operator '{}'", word))?,
+        _ => return Err("expected an operator".to_string()),
+    };
+
+    let operand = tokens.next();
+    if tokens.next().is_some() {
+        return Err("unexpected input after value".to_string());
+    }
+
+    let literal = compile_operand(&path, attr.kind(), op, operand)?;
+    Ok(Test { source: source.trim().to_string(), attr, op, literal })
+}
+
+fn compile_operand(path: &str, kind: Kind, op: Op, operand: Option<Token>) -> Result<Literal, String> {
+    if op == Op::Exists {
+        return match operand {
+            None => Ok(Literal::None),
+            Some(_) => Err("'exists' takes no value".to_string()),
+        };
+    }
+    let operand = operand.ok_or_else(|| format!("'{}' needs a value", op.symbol()))?;
+
+    match (kind, op) {
+        (Kind::Str, Op::Eq | Op::Ne | Op::StartsWith | Op::EndsWith) => Ok(Literal::Str(scalar(operand)?)),
+        (Kind::Str, Op::In) => Ok(Literal::Strs(list(operand)?)),
+        (Kind::Str, Op::Matches) => {
+            let pattern = scalar(operand)?;
+            regex::Regex::new(&pattern)
+                .map(Literal::Regex)
+                .map_err(|e| format!("invalid pattern: {}", e))
+        }
+        (Kind::Enum(names), Op::Eq | Op::Ne) => canonical(names, &scalar(operand)?).map(Literal::Str),
+        (Kind::Enum(names), Op::In) => list(operand)?
+            .iter()
+            .map(|v| canonical(names, v))
+            .collect::<Result<_, _>>()
+            .map(Literal::Strs),
+        (Kind::Ordinal(names), Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge) => {
+            rank(names, &scalar(operand)?).map(Literal::Num)
+        }
+        (Kind::Ordinal(names), Op::In) => list(operand)?
+            .iter()
+            .map(|v| rank(names, v))
+            .collect::<Result<_, _>>()
+            .map(Literal::Nums),
+        (Kind::Num, Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge) => {
+            let value = scalar(operand)?;
+            value.parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(Literal::Num)
+                .ok_or_else(|| format!("expected a number, found '{}'", value))
+        }
+        (Kind::Bool, Op::Eq | Op::Ne) => match scalar(operand)?.as_str() {
+            "true" => Ok(Literal::Bool(true)),
+            "false" => Ok(Literal::Bool(false)),
+            other => Err(format!("expected true or false, found '{}'", other)),
+        },
+        (Kind::List(names), Op::Contains) => {
+            let values = one_or_many(operand)?;
+            match names {
+                Some(names) => values.iter()
+                    .map(|v| canonical(names, v))
+                    .collect::<Result<_, _>>()
+                    .map(Literal::Strs),
+                None => Ok(Literal::Strs(values)),
+            }
+        }
+        (Kind::Ip, Op::Eq | Op::Ne) => {
+            let value = scalar(operand)?;
+            value.parse::<IpAddr>()
+                .map(Literal::Ip)
+                .map_err(|_| format!("invalid address '{}'", value))
+        }
+        (Kind::Ip, Op::InCidr) => one_or_many(operand)?
+            .iter()
+            .map(|v| v.parse::<IpNetwork>().map_err(|_| format!("invalid CIDR '{}'", v)))
+            .collect::<Result<_, _>>()
+            .map(Literal::Cidrs),
+        (kind, op) => Err(format!(
+            "'{}' is not valid for {} attribute {}",
+            op.symbol(),
+            kind.name(),
+            path
+        )),
+    }
+}
+
+fn scalar(token: Token) -> Result<String, String> {
+    match token {
+        Token::Word(value) | Token::Quoted(value) => Ok(value),
+        Token::List(_) => Err("expected a single value, found a list".to_string()),
+        Token::Op(op) => Err(format!("expected a value, found '{}'", op.symbol())),
+    }
+}
+
+fn list(token: Token) -> Result<Vec<String>, String> {
+    match token {
+        Token::List(values) => Ok(values),
+        _ => Err("'in' expects a list such as [a, b]".to_string()),
+    }
+}
+
+fn one_or_many(token: Token) -> Result<Vec<String>, String> {
+    match token {
+        Token::List(values) => Ok(values),
+        other => scalar(other).map(|v| vec![v]),
+    }
+}
+
+/// Match a name case-insensitively against a fixed set
+fn canonical(names: &[&str], value: &str) -> Result<String, String> {
+    names.iter()
+        .find(|n| n.eq_ignore_ascii_case(value))
+        .map(|n| n.to_string())
+        .ok_or_else(|| format!("'{}' is not one of {}", value, names.join(", ")))
+}
+
+/// Rank of a named level, or a level given by number
+fn rank(names: &[&str], value: &str) -> Result<f64, String> {
+    names.iter()
+        .position(|n| n.eq_ignore_ascii_case(value))
+        .or_else(|| value.parse::<usize>().ok().filter(|i| *i < names.len()))
+        .map(|i| i as f64)
+        .ok_or_else(|| format!("'{}' is not one of {}", value, names.join(", ")))
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                chars.next();
+                tokens.push(Token::Quoted(read_quoted(&mut chars, c)?));
+            }
+            '[' => {
+                chars.next();
+                tokens.push(Token::List(read_list(&mut chars)?));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                let op = match (c, eq) {
+                    ('=', true) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return Err(format!("unexpected '{}'", c)),
+                };
+                tokens.push(Token::Op(op));
+            }
+            c if is_word_char(c) => tokens.push(Token::Word(read_word(&mut chars))),
+            c => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '[' | ']' | ',' | '\'' | '"' | '=' | '!' | '<' | '>')
+}
+
+fn read_word(chars: &mut Chars) -> String {
+    let mut word = String::new();
+    while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+        word.push(c);
+    }
+    word
+}
+
+fn read_quoted(chars: &mut Chars, quote: char) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('\\') => match chars.next() {
+                Some(escaped) => value.push(escaped),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) if c == quote => return Ok(value),
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+fn read_list(chars: &mut Chars) -> Result<Vec<String>, String> {
+    let mut items = Vec::new();
+    loop {
+        skip_whitespace(chars);
+        match chars.peek().copied() {
+            None => return Err("unterminated list".to_string()),
+            Some(']') if items.is_empty() => {
+                chars.next();
+                return Ok(items);
+            }
+            Some(q @ ('\'' | '"')) => {
+                chars.next();
+                items.push(read_quoted(chars, q)?);
+            }
+            Some(c) if is_word_char(c) => items.push(read_word(chars)),
+            Some(c) => return Err(format!("unexpected '{}' in list", c)),
+        }
+
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Ok(items),
+            Some(c) => return Err(format!("expected ',' or ']' in list, found '{}'", c)),
+            None => return Err("unterminated list".to_string()),
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Chars) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::testing::RequestFixture;
+    use crate::policy::PolicyEngine;
+
+    fn request(fixture: &str) -> AccessRequest {
+        serde_yaml::from_str::<RequestFixture>(fixture).unwrap().build().unwrap()
+    }
+
+    fn compile_error(clause: &str) -> String {
+        let source = format!("policies:\n  - id: p\n    effect: allow\n    when: \"{}\"\n", clause);
+        match PolicySet::parse(&source) {
+            Err(PolicyLangError::Policy { policy, message }) => {
+                assert_eq!(policy, "p");
+                let prefix = format!("`{}`: ", clause);
+                message.strip_prefix(&prefix).map(str::to_string).unwrap_or(message)
+            }
+            other => panic!("expected a policy error for {:?}, got {:?}", clause, other),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_clause() {
+        let tokens = tokenize("resource.tags.env in ['prod', \"stage 2\", dev]").unwrap();
+        assert!(matches!(&tokens[0], Token::Word(w) if w == "resource.tags.env"));
+        assert!(matches!(&tokens[1], Token::Word(w) if w == "in"));
+        assert!(matches!(&tokens[2], Token::List(v) if v == &["prod", "stage 2", "dev"]));
+
+        let tokens = tokenize("context.hour>=7").unwrap();
+        assert!(matches!(tokens[1], Token::Op(Op::Ge)));
+        assert!(matches!(&tokens[2], Token::Word(w) if w == "7"));
+
+        assert_eq!(tokenize("identity.email == 'x").unwrap_err(), "unterminated string");
+        assert_eq!(tokenize("identity.groups contains [a, b").unwrap_err(), "unterminated list");
+        assert_eq!(tokenize("context.hour =< 7").unwrap_err(), "unexpected '='");
+    }
+
+    #[test]
+    fn test_parse_clause() {
+        let test = parse_clause("  device.trust_level >= medium ").unwrap();
+        assert_eq!(test.source, "device.trust_level >= medium");
+        assert!(matches!(test.attr, Attr::DeviceTrust));
+        assert_eq!(test.op, Op::Ge);
+        assert!(matches!(test.literal, Literal::Num(n) if n == 2.0));
+
+        // Enum literals are matched case-insensitively and stored canonically
+        let test = parse_clause("device.type in [laptop, IOT]").unwrap();
+        assert!(matches!(&test.literal, Literal::Strs(v) if v == &["Laptop", "IoT"]));
+
+        let test = parse_clause("context.ip in_cidr 10.0.0.0/8").unwrap();
+        assert!(matches!(&test.literal, Literal::Cidrs(v) if v.len() == 1));
+
+        let test = parse_clause("resource.tags.env exists").unwrap();
+        assert!(matches!(test.attr, Attr::ResourceTag(ref key) if key == "env"));
+        assert!(matches!(test.literal, Literal::None));
+    }
+
+    #[test]
+    fn test_type_check_errors() {
+        assert_eq!(compile_error("device.colour == red"), "unknown attribute 'device.colour'");
+        assert_eq!(compile_error("resource.tags. == x"), "unknown attribute 'resource.tags.'");
+        assert_eq!(compile_error("context.hour around 7"), "unknown operator 'around'");
+        assert_eq!(compile_error("context.hour >= 7 8"), "unexpected input after value");
+        assert_eq!(compile_error("context.hour >= seven"), "expected a number, found 'seven'");
+        assert_eq!(compile_error("device.managed == yes"), "expected true or false, found 'yes'");
+        assert_eq!(compile_error("identity.email == [a, b]"), "expected a single value, found a list");
+        assert_eq!(compile_error("context.country in KP"), "'in' expects a list such as [a, b]");
+        assert_eq!(compile_error("context.ip in_cidr 10.0.0.0/33"), "invalid CIDR '10.0.0.0/33'");
+        assert_eq!(compile_error("resource.tags.env exists prod"), "'exists' takes no value");
+        assert_eq!(compile_error("context.hour >="), "'>=' needs a value");
+        assert_eq!(
+            compile_error("device.managed >= true"),
+            "'>=' is not valid for boolean attribute device.managed",
+        );
+        assert!(compile_error("device.type == Phone").starts_with("'Phone' is not one of Desktop, Laptop"));
+        assert!(compile_error("context.signals contains Phishing").starts_with("'Phishing' is not one of"));
+        assert!(compile_error("resource.sensitivity > Secret").starts_with("'Secret' is not one of Public"));
+        assert!(compile_error("identity.email matches '('").starts_with("invalid pattern"));
+    }
+
+    #[test]
+    fn test_document_errors() {
+        let duplicate = "policies:\n  - id: a\n    effect: allow\n  - id: a\n    effect: deny\n";
+        assert!(matches!(PolicySet::parse(duplicate), Err(PolicyLangError::DuplicateId(id)) if id == "a"));
+
+        let default = "default: challenge\npolicies: []\n";
+        assert!(matches!(PolicySet::parse(default), Err(PolicyLangError::InvalidDefault(PolicyEffect::Challenge))));
+
+        let unknown = "policies:\n  - id: a\n    effect: allow\n    wen: device.managed == true\n";
+        assert!(matches!(PolicySet::parse(unknown), Err(PolicyLangError::Syntax(_))));
+
+        // Disabled policies are still type-checked
+        let disabled = "policies:\n  - id: a\n    effect: allow\n    enabled: false\n    when: device.managed == maybe\n";
+        assert!(matches!(PolicySet::parse(disabled), Err(PolicyLangError::Policy { .. })));
+    }
+
+    #[test]
+    fn test_enum_attributes_use_canonical_names() {
+        let set = PolicySet::parse(r#"
+policies:
+  - id: enums
+    effect: allow
+    when:
+      all:
+        - device.type == tablet
+        - resource.type == FileShare
+        - action == delete
+        - context.network == publicwifi
+        - context.weekday == mon
+        - context.signals contains [newdevice, BruteForceAttempt]
+        - identity.provider == local
+"#).unwrap();
+
+        let matching = request(r#"
+device: { type: Tablet }
+resource: { type: FileShare }
+action: Delete
+context: { network: PublicWifi, signals: [NewDevice, BruteForceAttempt] }
+"#);
+        assert_eq!(set.evaluate(&matching).outcome, Outcome::Allow);
+
+        let other = request("device: { type: IoT }\nresource: { type: FileShare }\naction: Delete\n");
+        assert_eq!(set.evaluate(&other).outcome, Outcome::NotApplicable);
+    }
+
+    #[test]
+    fn test_deny_overrides() {
+        let set = PolicySet::parse(r#"
+default: allow
+policies:
+  - id: allow-engineering
+    effect: allow
+    when: identity.groups contains engineering
+    obligations: [RequireMfa]
+  - id: challenge-untrusted
+    effect: challenge
+    when: device.trust_level < Medium
+  - id: deny-jailbroken
+    effect: deny
+    when: device.posture.jailbroken == true
+  - id: audit-prod
+    effect: audit
+    when: resource.tags.env == prod
+"#).unwrap();
+
+        let allowed = set.evaluate(&request("identity: { groups: [engineering] }\nresource: { tags: { env: prod } }\n"));
+        assert_eq!(allowed.outcome, Outcome::Allow);
+        assert_eq!(allowed.determining, vec!["allow-engineering"]);
+        assert_eq!(allowed.audited, vec!["audit-prod"]);
+        assert_eq!(allowed.obligations.len(), 1);
+
+        let challenged = set.evaluate(&request("identity: { groups: [engineering] }\ndevice: { trust_level: Low }\n"));
+        assert_eq!(challenged.outcome, Outcome::Challenge);
+        assert_eq!(challenged.determining, vec!["challenge-untrusted"]);
+        assert!(challenged.obligations.is_empty());
+
+        let denied = set.evaluate(&request(
+            "identity: { groups: [engineering] }\ndevice: { trust_level: Low, posture: { jailbroken: true } }\n",
+        ));
+        assert_eq!(denied.outcome, Outcome::Deny);
+        assert_eq!(denied.determining, vec!["deny-jailbroken"]);
+        assert_eq!(denied.matched.len(), 3);
+
+        // Audit alone never decides; the default applies
+        let audited = set.evaluate(&request("resource: { tags: { env: prod } }\n"));
+        assert_eq!(audited.outcome, Outcome::Allow);
+        assert!(audited.determining.is_empty());
+        assert_eq!(audited.reasons, vec!["No ABAC policy matched; default allow"]);
+    }
+
+    #[test]
+    fn test_missing_attributes_fail_except_exists() {
+        let set = PolicySet::parse(r#"
+policies:
+  - id: not-kp
+    effect: deny
+    when:
+      not: context.country in [KP]
+  - id: tagged
+    effect: audit
+    when: resource.tags.env exists
+"#).unwrap();
+
+        // No geo fix: the comparison fails, so the negation matches
+        let unknown = set.evaluate(&request("{}"));
+        assert_eq!(unknown.matched, vec!["not-kp"]);
+
+        let located = set.evaluate(&request("context: { country: KP }\nresource: { tags: { env: dev } }\n"));
+        assert_eq!(located.matched, vec!["tagged"]);
+    }
+
+    #[test]
+    fn test_simulation_trace_short_circuits() {
+        let set = PolicySet::parse(r#"
+policies:
+  - id: managed-office
+    effect: allow
+    when:
+      all:
+        - device.managed == true
+        - context.hour >= 7
+        - context.network == Corporate
+"#).unwrap();
+
+        let simulation = set.simulate(&request("context: { hour: 5 }\n"));
+        assert_eq!(simulation.decision.outcome, Outcome::NotApplicable);
+        let clauses = &simulation.trace[0].clauses;
+        assert_eq!(clauses.len(), 2);
+        assert_eq!((clauses[0].observed.as_str(), clauses[0].result), ("true", true));
+        assert_eq!((clauses[1].observed.as_str(), clauses[1].result), ("5", false));
+    }
+
+    #[test]
+    fn test_dry_run_reports_changed_outcomes() {
+        let engine = PolicyEngine::new();
+        engine.load_abac("default: deny\npolicies:\n  - id: eng\n    effect: allow\n    when: identity.groups contains engineering\n").unwrap();
+
+        let mut engineer = request("identity: { groups: [engineering] }\ndevice: { managed: false }\n");
+        engineer.id = "engineer".to_string();
+        let mut managed = request("identity: { groups: [engineering] }\n");
+        managed.id = "managed".to_string();
+
+        let candidate = r#"
+default: deny
+policies:
+  - id: eng-managed
+    effect: allow
+    when:
+      all:
+        - identity.groups contains engineering
+        - device.managed == true
+"#;
+        let results = engine.dry_run(candidate, &[engineer, managed]).unwrap();
+        assert_eq!(results[0].request_id, "engineer");
+        assert_eq!(results[0].current, Outcome::Allow);
+        assert_eq!(results[0].simulation.decision.outcome, Outcome::Deny);
+        assert!(results[0].changed);
+        assert!(!results[1].changed);
+        assert_eq!(results[1].simulation.decision.determining, vec!["eng-managed"]);
+
+        // The installed set is untouched and a bad candidate is rejected
+        assert_eq!(engine.abac_policies().unwrap().policies()[0].id, "eng");
+        assert!(engine.dry_run("policies: [", &[]).is_err());
+    }
+}
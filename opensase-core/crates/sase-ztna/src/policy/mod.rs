@@ -1,9 +1,20 @@
 //! Policy Engine
 //!
-//! Zero Trust policy evaluation with ABAC and RBAC support.
+//! Zero Trust policy evaluation with ABAC and RBAC support. Built-in
+//! policies are defined in code; declarative ABAC policies can be loaded at
+//! runtime from the policy language in [`lang`] or the Cedar subset in
+//! [`cedar`], tested with [`testing`] suites, and kept in sync with a Git
+//! repository by [`store`].
+
+pub mod cedar;
+pub mod lang;
+pub mod store;
+pub mod testing;
 
 use crate::{AccessRequest, AccessDecision, Decision, AccessCondition, DataSensitivity};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Policy decision engine
 pub struct PolicyEngine {
@@ -13,6 +24,8 @@ pub struct PolicyEngine {
     roles: dashmap::DashMap<String, Role>,
     /// Resource policies
     resource_policies: dashmap::DashMap<String, ResourcePolicy>,
+    /// Compiled declarative policies, swapped whole on reload
+    abac: parking_lot::RwLock<Option<Arc<lang::PolicySet>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -56,7 +69,8 @@ pub enum PolicyCondition {
     Not(Box<PolicyCondition>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PolicyEffect {
     Allow,
     Deny,
@@ -103,6 +117,7 @@ impl PolicyEngine {
             policies: dashmap::DashMap::new(),
             roles: dashmap::DashMap::new(),
             resource_policies: dashmap::DashMap::new(),
+            abac: parking_lot::RwLock::new(None),
         };
         
         // Add default policies
@@ -206,6 +221,34 @@ impl PolicyEngine {
             }
         }
         
+        // Declarative ABAC policies (deny-overrides within the set)
+        let abac = self.abac.read().clone();
+        if let Some(set) = abac {
+            let result = set.evaluate(request);
+            for id in &result.audited {
+                tracing::info!("ABAC audit policy matched: {}", id);
+            }
+            matching_policies.extend(result.matched);
+            
+            match result.outcome {
+                lang::Outcome::Deny => {
+                    decision = Decision::Deny;
+                    reasons.extend(result.reasons);
+                }
+                lang::Outcome::Challenge => {
+                    if decision != Decision::Deny {
+                        decision = Decision::Challenge;
+                        reasons.extend(result.reasons);
+                    }
+                }
+                lang::Outcome::Allow => {
+                    all_conditions.extend(result.obligations);
+                    reasons.extend(result.reasons);
+                }
+                lang::Outcome::NotApplicable => {}
+            }
+        }
+        
         // Check resource-specific policy
         if let Some(resource_policy) = self.resource_policies.get(&request.resource.id) {
             let rp_result = self.evaluate_resource_policy(&resource_policy, request);
@@ -348,6 +391,71 @@ impl PolicyEngine {
     pub fn add_resource_policy(&self, policy: ResourcePolicy) {
         self.resource_policies.insert(policy.resource_id.clone(), policy);
     }
+    
+    /// Compile and install an ABAC policy document, replacing the current
+    /// one. Nothing changes if the document fails to compile.
+    pub fn load_abac(&self, source: &str) -> Result<usize, lang::PolicyLangError> {
+        self.load_abac_as(source, lang::PolicyFormat::Yaml)
+    }
+    
+    /// [`load_abac`](Self::load_abac) for a document in either language
+    pub fn load_abac_as(&self, source: &str, format: lang::PolicyFormat) -> Result<usize, lang::PolicyLangError> {
+        let set = lang::PolicySet::parse_as(source, format)?;
+        Ok(self.install_abac(set))
+    }
+    
+    /// Install a compiled policy set, replacing the current one
+    pub fn install_abac(&self, set: lang::PolicySet) -> usize {
+        let count = set.len();
+        *self.abac.write() = Some(Arc::new(set));
+        tracing::info!("Loaded {} ABAC policies", count);
+        count
+    }
+    
+    /// Remove the installed ABAC policy document
+    pub fn clear_abac(&self) {
+        *self.abac.write() = None;
+    }
+    
+    /// Currently installed ABAC policies
+    pub fn abac_policies(&self) -> Option<Arc<lang::PolicySet>> {
+        self.abac.read().clone()
+    }
+    
+    /// Run test suites against the installed ABAC policies
+    pub fn test_abac(&self, suites: &[testing::PolicyTestSuite]) -> Option<testing::TestReport> {
+        self.abac_policies().map(|set| testing::run_tests(&set, suites))
+    }
+    
+    /// Trace a request through the installed ABAC policies without
+    /// enforcing or auditing anything
+    pub fn simulate(&self, request: &AccessRequest) -> Option<lang::Simulation> {
+        self.abac.read().as_ref().map(|set| set.simulate(request))
+    }
+    
+    /// Replay requests against a candidate ABAC document and report which
+    /// outcomes would change relative to the installed one
+    pub fn dry_run(
+        &self,
+        source: &str,
+        requests: &[AccessRequest],
+    ) -> Result<Vec<lang::DryRunResult>, lang::PolicyLangError> {
+        let candidate = lang::PolicySet::parse(source)?;
+        let installed = self.abac_policies();
+        
+        Ok(requests.iter().map(|request| {
+            let current = installed.as_ref()
+                .map(|set| set.evaluate(request).outcome)
+                .unwrap_or(lang::Outcome::NotApplicable);
+            let simulation = candidate.simulate(request);
+            lang::DryRunResult {
+                request_id: request.id.clone(),
+                changed: simulation.decision.outcome != current,
+                current,
+                simulation,
+            }
+        }).collect())
+    }
 }
 
 impl Default for PolicyEngine {
@@ -0,0 +1,380 @@
+//! Git-backed Policy Store
+//!
+//! Policy as code: a Git repository, or a directory inside one, holds YAML
+//! and Cedar policy documents next to `*.test.yaml` suites. The store polls
+//! a branch; when its head moves, every document is compiled into a single
+//! policy set, the suites run against it, and the set is installed in the
+//! [`PolicyEngine`]. A revision that fails to fetch, compile or pass its
+//! tests is rejected and the running policies stay in place.
+//!
+//! Git is driven through the `git` binary so deployments authenticate the
+//! way they already do (SSH agent, credential helper, token in the URL).
+
+use super::lang::{PolicyDocument, PolicyFormat, PolicyLangError, PolicySet};
+use super::testing::{run_tests, PolicyTestSuite, TestReport};
+use super::PolicyEngine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct GitPolicyConfig {
+    /// Repository to clone
+    pub url: String,
+    pub branch: String,
+    /// Local working copy, cloned on first sync
+    pub checkout_dir: PathBuf,
+    /// Directory within the repository holding the policies
+    pub path: Option<String>,
+    pub poll_interval_secs: u64,
+    /// Reject revisions with failing test suites; when off, failures are
+    /// only logged
+    pub require_passing_tests: bool,
+    pub git_timeout_secs: u64,
+}
+
+impl GitPolicyConfig {
+    pub fn new(url: &str, checkout_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            url: url.to_string(),
+            branch: "main".to_string(),
+            checkout_dir: checkout_dir.into(),
+            path: None,
+            poll_interval_secs: 60,
+            require_passing_tests: true,
+            git_timeout_secs: 120,
+        }
+    }
+}
+
+/// Installed revision of the policy repository
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyRevision {
+    pub commit: String,
+    pub loaded_at: DateTime<Utc>,
+    /// Policy documents, relative to the policy directory
+    pub files: Vec<String>,
+    pub policies: usize,
+    /// Test results, when the revision has suites
+    pub tests: Option<TestReport>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PolicyStoreError {
+    Git(String),
+    Io(String),
+    /// A document failed to parse or compile
+    Policy { file: String, error: PolicyLangError },
+    /// Documents contradict each other (duplicate ids, differing defaults)
+    Conflict(String),
+    TestsFailed(TestReport),
+}
+
+impl std::fmt::Display for PolicyStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Git(e) => write!(f, "Git error: {}", e),
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Policy { file, error } => write!(f, "{}: {}", file, error),
+            Self::Conflict(e) => write!(f, "Conflicting policy documents: {}", e),
+            Self::TestsFailed(report) => {
+                write!(f, "{} of {} policy tests failed", report.failed, report.total)?;
+                if let Some(first) = report.failures().first() {
+                    write!(f, " ({})", first)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyStoreError {}
+
+// =============================================================================
+// Policy Bundle
+// =============================================================================
+
+/// Compiled documents and test suites read from a directory tree
+pub struct PolicyBundle {
+    pub set: PolicySet,
+    /// Policy documents, relative to the directory
+    pub files: Vec<String>,
+    pub suites: Vec<PolicyTestSuite>,
+}
+
+impl PolicyBundle {
+    /// Read every `.yaml` / `.yml` / `.cedar` document and `*.test.yaml`
+    /// suite under a directory, skipping hidden entries
+    pub fn load_dir(root: &Path) -> Result<Self, PolicyStoreError> {
+        let mut paths = Vec::new();
+        collect_files(root, &mut paths)?;
+        paths.sort();
+
+        let mut merged = PolicyDocument { default: None, policies: Vec::new() };
+        let mut default_from: Option<String> = None;
+        let mut owners: HashMap<String, String> = HashMap::new();
+        let mut files = Vec::new();
+        let mut suites = Vec::new();
+
+        for path in paths {
+            let name = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+            let is_suite = PolicyTestSuite::is_test_file(&name);
+            let format = match PolicyFormat::from_path(&path) {
+                Some(format) => format,
+                None => continue,
+            };
+            let source = std::fs::read_to_string(&path)
+                .map_err(|e| PolicyStoreError::Io(format!("{}: {}", name, e)))?;
+
+            if is_suite {
+                suites.push(PolicyTestSuite::parse(&name, &source)
+                    .map_err(|error| PolicyStoreError::Policy { file: name.clone(), error })?);
+                continue;
+            }
+
+            let document = PolicyDocument::parse(&source, format)
+                .map_err(|error| PolicyStoreError::Policy { file: name.clone(), error })?;
+            if let Some(effect) = document.default {
+                if let (Some(current), Some(from)) = (merged.default, &default_from) {
+                    if current != effect {
+                        return Err(PolicyStoreError::Conflict(format!(
+                            "{} and {} set different defaults", from, name
+                        )));
+                    }
+                }
+                merged.default = Some(effect);
+                default_from = Some(name.clone());
+            }
+            for policy in &document.policies {
+                if let Some(other) = owners.insert(policy.id.clone(), name.clone()) {
+                    return Err(PolicyStoreError::Conflict(format!(
+                        "policy {} defined in both {} and {}", policy.id, other, name
+                    )));
+                }
+            }
+            merged.policies.extend(document.policies);
+            files.push(name);
+        }
+
+        let set = PolicySet::compile(merged).map_err(|error| {
+            let file = match &error {
+                PolicyLangError::Policy { policy, .. } => owners.get(policy).cloned(),
+                _ => None,
+            };
+            PolicyStoreError::Policy { file: file.unwrap_or_else(|| root.display().to_string()), error }
+        })?;
+
+        Ok(Self { set, files, suites })
+    }
+
+    pub fn run_tests(&self) -> TestReport {
+        run_tests(&self.set, &self.suites)
+    }
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), PolicyStoreError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| PolicyStoreError::Io(format!("{}: {}", dir.display(), e)))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| PolicyStoreError::Io(format!("{}: {}", dir.display(), e)))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()
+            .map_err(|e| PolicyStoreError::Io(format!("{}: {}", path.display(), e)))?;
+        if file_type.is_dir() {
+            collect_files(&path, paths)?;
+        } else if file_type.is_file() {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Git Store
+// =============================================================================
+
+/// Keeps a policy engine in sync with a Git branch
+pub struct GitPolicyStore {
+    config: GitPolicyConfig,
+    engine: Arc<PolicyEngine>,
+    state: parking_lot::RwLock<StoreState>,
+    /// One sync at a time in the working copy
+    sync_lock: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct StoreState {
+    revision: Option<PolicyRevision>,
+    /// Head that failed to load, not retried until the branch moves
+    rejected: Option<String>,
+    last_error: Option<String>,
+    last_checked: Option<DateTime<Utc>>,
+}
+
+impl GitPolicyStore {
+    pub fn new(config: GitPolicyConfig, engine: Arc<PolicyEngine>) -> Self {
+        Self {
+            config,
+            engine,
+            state: parking_lot::RwLock::new(StoreState::default()),
+            sync_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Fetch the branch and install its policies if the head moved.
+    /// Returns the new revision, or `None` when nothing changed.
+    pub async fn sync(&self) -> Result<Option<PolicyRevision>, PolicyStoreError> {
+        let _guard = self.sync_lock.lock().await;
+
+        let commit = match self.fetch().await {
+            Ok(commit) => commit,
+            Err(e) => {
+                let mut state = self.state.write();
+                state.last_checked = Some(Utc::now());
+                state.last_error = Some(e.to_string());
+                return Err(e);
+            }
+        };
+        {
+            let mut state = self.state.write();
+            state.last_checked = Some(Utc::now());
+            let installed = state.revision.as_ref().is_some_and(|r| r.commit == commit);
+            if installed || state.rejected.as_deref() == Some(commit.as_str()) {
+                return Ok(None);
+            }
+        }
+
+        match self.load(&commit).await {
+            Ok(revision) => {
+                let mut state = self.state.write();
+                state.revision = Some(revision.clone());
+                state.rejected = None;
+                state.last_error = None;
+                Ok(Some(revision))
+            }
+            Err(e) => {
+                let mut state = self.state.write();
+                state.rejected = Some(commit);
+                state.last_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    async fn load(&self, commit: &str) -> Result<PolicyRevision, PolicyStoreError> {
+        let root = match &self.config.path {
+            Some(path) => self.config.checkout_dir.join(path),
+            None => self.config.checkout_dir.clone(),
+        };
+        let bundle = tokio::task::spawn_blocking(move || PolicyBundle::load_dir(&root))
+            .await
+            .map_err(|e| PolicyStoreError::Io(e.to_string()))??;
+
+        let tests = (!bundle.suites.is_empty()).then(|| bundle.run_tests());
+        if let Some(report) = tests.as_ref().filter(|r| !r.is_success()) {
+            if self.config.require_passing_tests {
+                return Err(PolicyStoreError::TestsFailed(report.clone()));
+            }
+            for failure in report.failures() {
+                tracing::warn!("Policy test failed at {}: {}", commit, failure);
+            }
+        }
+
+        let files = bundle.files;
+        let policies = self.engine.install_abac(bundle.set);
+        tracing::info!("Installed {} ABAC policies from {} at {}", policies, self.config.url, commit);
+
+        Ok(PolicyRevision {
+            commit: commit.to_string(),
+            loaded_at: Utc::now(),
+            files,
+            policies,
+            tests,
+        })
+    }
+
+    /// Bring the working copy to the branch head; returns the head commit
+    async fn fetch(&self) -> Result<String, PolicyStoreError> {
+        let dir = &self.config.checkout_dir;
+        let branch = self.config.branch.as_str();
+
+        if tokio::fs::try_exists(dir.join(".git")).await.unwrap_or(false) {
+            self.git(Some(dir), &["fetch", "--quiet", "--depth", "1", "origin", branch]).await?;
+            self.git(Some(dir), &["reset", "--quiet", "--hard", "FETCH_HEAD"]).await?;
+            self.git(Some(dir), &["clean", "--quiet", "-fd"]).await?;
+        } else {
+            if let Some(parent) = dir.parent() {
+                tokio::fs::create_dir_all(parent).await
+                    .map_err(|e| PolicyStoreError::Io(format!("{}: {}", parent.display(), e)))?;
+            }
+            let target = dir.to_string_lossy().into_owned();
+            self.git(None, &[
+                "clone", "--quiet", "--depth", "1", "--single-branch", "--branch", branch,
+                self.config.url.as_str(), target.as_str(),
+            ]).await?;
+        }
+
+        let head = self.git(Some(dir), &["rev-parse", "HEAD"]).await?;
+        Ok(head.trim().to_string())
+    }
+
+    async fn git(&self, dir: Option<&Path>, args: &[&str]) -> Result<String, PolicyStoreError> {
+        let verb = args.first().copied().unwrap_or_default();
+        let mut command = tokio::process::Command::new("git");
+        if let Some(dir) = dir {
+            command.arg("-C").arg(dir);
+        }
+        command.args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let output = tokio::time::timeout(Duration::from_secs(self.config.git_timeout_secs), command.output())
+            .await
+            .map_err(|_| PolicyStoreError::Git(format!("git {} timed out", verb)))?
+            .map_err(|e| PolicyStoreError::Git(format!("git {}: {}", verb, e)))?;
+        if !output.status.success() {
+            return Err(PolicyStoreError::Git(format!(
+                "git {} failed: {}",
+                verb,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Poll the branch for new revisions
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(self.config.poll_interval_secs.max(1)));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.sync().await {
+                    tracing::warn!("Policy sync from {} failed: {}", self.config.url, e);
+                }
+            }
+        })
+    }
+
+    /// Revision currently installed
+    pub fn revision(&self) -> Option<PolicyRevision> {
+        self.state.read().revision.clone()
+    }
+
+    /// Why the latest fetch or revision was rejected
+    pub fn last_error(&self) -> Option<String> {
+        self.state.read().last_error.clone()
+    }
+
+    pub fn last_checked(&self) -> Option<DateTime<Utc>> {
+        self.state.read().last_checked
+    }
+}
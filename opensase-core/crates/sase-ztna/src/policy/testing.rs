@@ -0,0 +1,491 @@
+//! Policy Tests
+//!
+//! Test cases pinning down what a policy document decides for sample
+//! requests. They live next to the policies as `*.test.yaml` files and run
+//! before a document is installed:
+//!
+//! ```yaml
+//! tests:
+//!   - name: non-compliant laptop can't reach confidential data
+//!     request:
+//!       device: { compliant: false }
+//!       resource: { sensitivity: Confidential }
+//!     expect: deny
+//!     determining: [noncompliant-confidential]
+//!   - name: engineers reach prod with MFA
+//!     request:
+//!       identity: { groups: [engineering] }
+//!       resource: { tags: { env: prod } }
+//!     expect: allow
+//!     obligations: [RequireMfa]
+//! ```
+//!
+//! Each request starts from a baseline (a managed, compliant, high-trust
+//! laptop on the corporate network reading an internal application at
+//! 12:00 UTC on a Monday, no geolocation, no risk) and overrides only the
+//! attributes the case is about.
+
+use super::lang::{Outcome, PolicyLangError, PolicySet};
+use crate::{
+    AccessAction, AccessCondition, AccessContext, AccessRequest, DataSensitivity, Device,
+    DevicePosture, DeviceType, GeoLocation, Identity, IdentityProvider, NetworkType, Resource,
+    ResourceType, RiskSeverity, RiskSignal, RiskSignalType, TrustLevel,
+};
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// File name suffixes of test suites
+pub const TEST_SUFFIXES: &[&str] = &[".test.yaml", ".test.yml"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyTestSuite {
+    /// Suite name, usually the file it was loaded from
+    #[serde(default)]
+    pub name: String,
+    pub tests: Vec<PolicyTestCase>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyTestCase {
+    pub name: String,
+    #[serde(default)]
+    pub request: RequestFixture,
+    pub expect: ExpectedOutcome,
+    /// Policies that must produce the outcome, in any order
+    #[serde(default)]
+    pub determining: Option<Vec<String>>,
+    /// Access conditions the decision must carry
+    #[serde(default)]
+    pub obligations: Vec<AccessCondition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedOutcome {
+    Allow,
+    Deny,
+    Challenge,
+    NotApplicable,
+}
+
+impl From<ExpectedOutcome> for Outcome {
+    fn from(expected: ExpectedOutcome) -> Self {
+        match expected {
+            ExpectedOutcome::Allow => Outcome::Allow,
+            ExpectedOutcome::Deny => Outcome::Deny,
+            ExpectedOutcome::Challenge => Outcome::Challenge,
+            ExpectedOutcome::NotApplicable => Outcome::NotApplicable,
+        }
+    }
+}
+
+// =============================================================================
+// Request Fixtures
+// =============================================================================
+
+/// Overrides applied to the baseline request; field names follow the
+/// policy language's attributes
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestFixture {
+    pub identity: IdentityFixture,
+    pub device: DeviceFixture,
+    pub resource: ResourceFixture,
+    pub action: Option<AccessAction>,
+    pub context: ContextFixture,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdentityFixture {
+    pub user_id: Option<String>,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+    pub roles: Vec<String>,
+    pub attributes: HashMap<String, String>,
+    pub mfa_verified: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceFixture {
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub device_type: Option<DeviceType>,
+    pub os: Option<String>,
+    pub os_version: Option<String>,
+    pub managed: Option<bool>,
+    pub compliant: Option<bool>,
+    pub trust_level: Option<TrustLevel>,
+    pub posture: PostureFixture,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostureFixture {
+    pub firewall_enabled: Option<bool>,
+    pub antivirus_running: Option<bool>,
+    pub disk_encrypted: Option<bool>,
+    pub os_patched: Option<bool>,
+    pub screen_lock_enabled: Option<bool>,
+    pub jailbroken: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceFixture {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub resource_type: Option<ResourceType>,
+    pub sensitivity: Option<DataSensitivity>,
+    pub owner: Option<String>,
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContextFixture {
+    pub ip: Option<IpAddr>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub network: Option<NetworkType>,
+    /// Time of access
+    pub time: Option<DateTime<Utc>>,
+    /// Hour of the baseline day, when only the hour matters
+    pub hour: Option<u32>,
+    pub risk_score: Option<f64>,
+    pub signals: Vec<RiskSignalType>,
+}
+
+impl RequestFixture {
+    /// Baseline request with this fixture's overrides applied
+    pub fn build(&self) -> Result<AccessRequest, String> {
+        let baseline_time = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .and_then(|d| d.and_hms_opt(12, 0, 0))
+            .map(|t| t.and_utc())
+            .ok_or("invalid baseline time")?;
+        let mut time = self.context.time.unwrap_or(baseline_time);
+        if let Some(hour) = self.context.hour {
+            time = time.with_hour(hour).ok_or_else(|| format!("context.hour {} out of range", hour))?;
+        }
+        if self.context.region.is_some() && self.context.country.is_none() {
+            return Err("context.region needs context.country".to_string());
+        }
+
+        let identity = &self.identity;
+        let user_id = identity.user_id.clone().unwrap_or_else(|| "test-user".to_string());
+        let device = &self.device;
+        let posture = &device.posture;
+        let resource = &self.resource;
+        let context = &self.context;
+
+        Ok(AccessRequest {
+            id: "policy-test".to_string(),
+            identity: Identity {
+                id: user_id.clone(),
+                email: identity.email.clone().unwrap_or_else(|| format!("{}@example.com", user_id)),
+                name: user_id.clone(),
+                user_id,
+                groups: identity.groups.clone(),
+                roles: identity.roles.clone(),
+                attributes: identity.attributes.clone(),
+                mfa_verified: identity.mfa_verified.unwrap_or(false),
+                verified_at: time,
+                provider: IdentityProvider::Local,
+            },
+            device: Device {
+                id: device.id.clone().unwrap_or_else(|| "test-device".to_string()),
+                name: "test-device".to_string(),
+                device_type: device.device_type.unwrap_or(DeviceType::Laptop),
+                os: device.os.clone().unwrap_or_else(|| "macOS".to_string()),
+                os_version: device.os_version.clone().unwrap_or_else(|| "14.0".to_string()),
+                managed: device.managed.unwrap_or(true),
+                compliant: device.compliant.unwrap_or(true),
+                trust_level: device.trust_level.unwrap_or(TrustLevel::High),
+                posture: DevicePosture {
+                    firewall_enabled: posture.firewall_enabled.unwrap_or(true),
+                    antivirus_running: posture.antivirus_running.unwrap_or(true),
+                    disk_encrypted: posture.disk_encrypted.unwrap_or(true),
+                    os_patched: posture.os_patched.unwrap_or(true),
+                    screen_lock_enabled: posture.screen_lock_enabled.unwrap_or(true),
+                    jailbroken: posture.jailbroken.unwrap_or(false),
+                    last_checked: time,
+                    antivirus_products: vec![],
+                    os_patch_age_days: None,
+                    attributes: HashMap::new(),
+                },
+                certificates: vec![],
+                last_seen: time,
+            },
+            resource: Resource {
+                id: resource.id.clone().unwrap_or_else(|| "test-resource".to_string()),
+                name: resource.name.clone().unwrap_or_else(|| "test-resource".to_string()),
+                resource_type: resource.resource_type.unwrap_or(ResourceType::Application),
+                sensitivity: resource.sensitivity.unwrap_or(DataSensitivity::Internal),
+                owner: resource.owner.clone().unwrap_or_else(|| "test-owner".to_string()),
+                tags: resource.tags.clone(),
+                access_policy: None,
+            },
+            action: self.action.unwrap_or(AccessAction::Read),
+            context: AccessContext {
+                client_ip: context.ip.unwrap_or(IpAddr::from([10, 0, 0, 10])),
+                geo_location: context.country.as_ref().map(|country| GeoLocation {
+                    country: country.clone(),
+                    region: context.region.clone(),
+                    city: None,
+                    latitude: 0.0,
+                    longitude: 0.0,
+                    asn: None,
+                    accuracy_km: None,
+                }),
+                network_type: context.network.unwrap_or(NetworkType::Corporate),
+                time_of_access: time,
+                session_id: None,
+                user_agent: "policy-test".to_string(),
+                risk_score: context.risk_score.unwrap_or(0.0),
+                signals: context.signals.iter().map(|signal_type| RiskSignal {
+                    signal_type: *signal_type,
+                    severity: RiskSeverity::Medium,
+                    description: "policy test".to_string(),
+                    detected_at: time,
+                }).collect(),
+            },
+            timestamp: time,
+        })
+    }
+}
+
+// =============================================================================
+// Running
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestReport {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<TestResult>,
+}
+
+impl TestReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// `suite: name: failure` lines for failed cases
+    pub fn failures(&self) -> Vec<String> {
+        self.results.iter()
+            .filter(|r| !r.passed)
+            .flat_map(|r| r.failures.iter().map(move |f| format!("{}: {}: {}", r.suite, r.name, f)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestResult {
+    pub suite: String,
+    pub name: String,
+    pub passed: bool,
+    pub expected: Outcome,
+    /// `None` when the request fixture was invalid
+    pub actual: Option<Outcome>,
+    pub determining: Vec<String>,
+    pub failures: Vec<String>,
+}
+
+impl PolicyTestSuite {
+    /// Parse a YAML test suite
+    pub fn parse(name: &str, source: &str) -> Result<Self, PolicyLangError> {
+        let mut suite: Self = serde_yaml::from_str(source)
+            .map_err(|e| PolicyLangError::Syntax(format!("{}: {}", name, e)))?;
+        if suite.name.is_empty() {
+            suite.name = name.to_string();
+        }
+        Ok(suite)
+    }
+
+    /// Whether a file name marks a test suite rather than a policy document
+    pub fn is_test_file(name: &str) -> bool {
+        TEST_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+    }
+}
+
+/// Run test suites against a compiled policy set
+pub fn run_tests(set: &PolicySet, suites: &[PolicyTestSuite]) -> TestReport {
+    let results: Vec<TestResult> = suites.iter()
+        .flat_map(|suite| suite.tests.iter().map(move |case| run_case(set, &suite.name, case)))
+        .collect();
+    let passed = results.iter().filter(|r| r.passed).count();
+
+    TestReport {
+        total: results.len(),
+        passed,
+        failed: results.len() - passed,
+        results,
+    }
+}
+
+fn run_case(set: &PolicySet, suite: &str, case: &PolicyTestCase) -> TestResult {
+    let mut result = TestResult {
+        suite: suite.to_string(),
+        name: case.name.clone(),
+        passed: false,
+        expected: case.expect.into(),
+        actual: None,
+        determining: vec![],
+        failures: vec![],
+    };
+
+    let request = match case.request.build() {
+        Ok(request) => request,
+        Err(e) => {
+            result.failures.push(format!("invalid request: {}", e));
+            return result;
+        }
+    };
+    let decision = set.evaluate(&request);
+
+    if decision.outcome != result.expected {
+        result.failures.push(format!("expected {:?}, got {:?}", result.expected, decision.outcome));
+    }
+    if let Some(expected) = &case.determining {
+        let mut want = expected.clone();
+        let mut got = decision.determining.clone();
+        want.sort();
+        got.sort();
+        if want != got {
+            result.failures.push(format!("expected policies [{}], got [{}]", want.join(", "), got.join(", ")));
+        }
+    }
+    // AccessCondition has no PartialEq; its Debug form is a faithful key
+    let obligations: Vec<String> = decision.obligations.iter().map(|c| format!("{:?}", c)).collect();
+    for obligation in &case.obligations {
+        let key = format!("{:?}", obligation);
+        if !obligations.contains(&key) {
+            result.failures.push(format!("missing obligation {}", key));
+        }
+    }
+
+    result.actual = Some(decision.outcome);
+    result.determining = decision.determining;
+    result.passed = result.failures.is_empty();
+    result
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICIES: &str = r#"
+default: deny
+policies:
+  - id: noncompliant-confidential
+    effect: deny
+    when:
+      all:
+        - device.compliant == false
+        - resource.sensitivity >= Confidential
+  - id: engineering-prod
+    effect: allow
+    when:
+      all:
+        - identity.groups contains engineering
+        - resource.tags.env == prod
+    obligations:
+      - RequireMfa
+"#;
+
+    fn run(suite: &str) -> TestReport {
+        let set = PolicySet::parse(POLICIES).unwrap();
+        run_tests(&set, &[PolicyTestSuite::parse("access.test.yaml", suite).unwrap()])
+    }
+
+    #[test]
+    fn test_passing_cases() {
+        let report = run(r#"
+tests:
+  - name: non-compliant laptop can't reach confidential data
+    request:
+      device: { compliant: false }
+      resource: { sensitivity: Confidential }
+    expect: deny
+    determining: [noncompliant-confidential]
+  - name: engineers reach prod with MFA
+    request:
+      identity: { groups: [engineering] }
+      resource: { tags: { env: prod } }
+    expect: allow
+    determining: [engineering-prod]
+    obligations: [RequireMfa]
+  - name: everyone else falls through to the default
+    expect: deny
+"#);
+
+        assert!(report.is_success(), "{:?}", report.failures());
+        assert_eq!((report.total, report.passed, report.failed), (3, 3, 0));
+        assert_eq!(report.results[0].suite, "access.test.yaml");
+        assert_eq!(report.results[1].actual, Some(Outcome::Allow));
+    }
+
+    #[test]
+    fn test_failing_cases_are_reported() {
+        let report = run(r#"
+tests:
+  - name: wrong outcome
+    request:
+      identity: { groups: [engineering] }
+      resource: { tags: { env: prod } }
+    expect: deny
+  - name: wrong policy and missing obligation
+    request:
+      identity: { groups: [engineering] }
+      resource: { tags: { env: prod } }
+    expect: allow
+    determining: [noncompliant-confidential]
+    obligations: [ReadOnly]
+  - name: invalid fixture
+    request:
+      context: { hour: 25 }
+    expect: deny
+"#);
+
+        assert!(!report.is_success());
+        assert_eq!((report.total, report.passed, report.failed), (3, 0, 3));
+
+        let wrong = &report.results[0];
+        assert_eq!(wrong.expected, Outcome::Deny);
+        assert_eq!(wrong.actual, Some(Outcome::Allow));
+        assert_eq!(wrong.failures, vec!["expected Deny, got Allow".to_string()]);
+
+        let mismatched = &report.results[1];
+        assert_eq!(mismatched.failures, vec![
+            "expected policies [noncompliant-confidential], got [engineering-prod]".to_string(),
+            "missing obligation ReadOnly".to_string(),
+        ]);
+
+        let invalid = &report.results[2];
+        assert_eq!(invalid.actual, None);
+        assert!(invalid.failures[0].starts_with("invalid request"));
+
+        let failures = report.failures();
+        assert_eq!(failures.len(), 4);
+        assert_eq!(failures[0], "access.test.yaml: wrong outcome: expected Deny, got Allow");
+    }
+
+    #[test]
+    fn test_suite_rejects_unknown_fields() {
+        let result = PolicyTestSuite::parse("typo.test.yaml", "tests:\n  - name: x\n    expect: allow\n    expected: deny\n");
+        assert!(matches!(result, Err(PolicyLangError::Syntax(message)) if message.starts_with("typo.test.yaml")));
+        assert!(PolicyTestSuite::is_test_file("policies/access.test.yml"));
+        assert!(!PolicyTestSuite::is_test_file("policies/access.yaml"));
+    }
+}
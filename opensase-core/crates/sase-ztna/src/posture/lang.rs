@@ -0,0 +1,617 @@
+//! Posture Expression Language
+//!
+//! A posture requirement is a boolean expression over the facts a device
+//! reports:
+//!
+//! ```text
+//! os_patched AND disk_encrypted AND antivirus in [CrowdStrike, Defender]
+//! NOT jailbroken AND (os != macOS OR os_version >= 14.2)
+//! patch_age_days <= 30 OR attr.exception == approved
+//! ```
+//!
+//! `AND` binds tighter than `OR`, `NOT` tighter than both; keywords are
+//! case-insensitive and parentheses group. Boolean facts stand alone (or
+//! compare with `== true`), text compares case-insensitively with `==` /
+//! `!=` / `in [..]`, `os_version` compares as a dotted version, and
+//! `antivirus` (the list of running products) matches with `in [..]` when
+//! any product is listed, or with `contains <product>`.
+//!
+//! Facts and literals are type-checked when the expression is parsed. A fact
+//! the device did not report (no patch age, absent attribute) fails every
+//! comparison.
+
+use super::version_compare;
+use crate::Device;
+
+const DEVICE_TYPES: &[&str] = &["Desktop", "Laptop", "Mobile", "Tablet", "Server", "IoT", "Unknown"];
+
+/// Compiled posture expression
+#[derive(Debug, Clone)]
+pub struct PostureExpr {
+    source: String,
+    node: Node,
+}
+
+impl PostureExpr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        if tokens.is_empty() {
+            return Err("empty expression".to_string());
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let node = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {}", token.describe()));
+        }
+        Ok(Self { source: source.trim().to_string(), node })
+    }
+
+    /// Expression as written
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate(&self, device: &Device) -> bool {
+        self.node.evaluate(device)
+    }
+}
+
+// =============================================================================
+// Facts
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bool,
+    Text,
+    Version,
+    Number,
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Fact {
+    FirewallEnabled,
+    AntivirusRunning,
+    DiskEncrypted,
+    OsPatched,
+    ScreenLockEnabled,
+    Jailbroken,
+    Managed,
+    Compliant,
+    CertificateValid,
+    Os,
+    OsVersion,
+    DeviceType,
+    Antivirus,
+    PatchAgeDays,
+    Attribute(String),
+}
+
+impl Fact {
+    fn parse(name: &str) -> Result<Self, String> {
+        if let Some(key) = name.strip_prefix("attr.") {
+            if key.is_empty() {
+                return Err("attribute name missing after 'attr.'".to_string());
+            }
+            return Ok(Self::Attribute(key.to_string()));
+        }
+        let fact = match name.to_ascii_lowercase().as_str() {
+            "firewall_enabled" | "firewall" => Self::FirewallEnabled,
+            "antivirus_running" => Self::AntivirusRunning,
+            "disk_encrypted" => Self::DiskEncrypted,
+            "os_patched" => Self::OsPatched,
+            "screen_lock_enabled" | "screen_lock" => Self::ScreenLockEnabled,
+            "jailbroken" => Self::Jailbroken,
+            "managed" => Self::Managed,
+            "compliant" => Self::Compliant,
+            "certificate_valid" => Self::CertificateValid,
+            "os" => Self::Os,
+            "os_version" => Self::OsVersion,
+            "device_type" => Self::DeviceType,
+            "antivirus" => Self::Antivirus,
+            "patch_age_days" => Self::PatchAgeDays,
+            _ => return Err(format!("unknown posture fact '{}'", name)),
+        };
+        Ok(fact)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Self::Os | Self::DeviceType | Self::Attribute(_) => Kind::Text,
+            Self::OsVersion => Kind::Version,
+            Self::Antivirus => Kind::List,
+            Self::PatchAgeDays => Kind::Number,
+            _ => Kind::Bool,
+        }
+    }
+
+    fn value(&self, device: &Device) -> Value {
+        let posture = &device.posture;
+        match self {
+            Self::FirewallEnabled => Value::Bool(posture.firewall_enabled),
+            Self::AntivirusRunning => Value::Bool(posture.antivirus_running),
+            Self::DiskEncrypted => Value::Bool(posture.disk_encrypted),
+            Self::OsPatched => Value::Bool(posture.os_patched),
+            Self::ScreenLockEnabled => Value::Bool(posture.screen_lock_enabled),
+            Self::Jailbroken => Value::Bool(posture.jailbroken),
+            Self::Managed => Value::Bool(device.managed),
+            Self::Compliant => Value::Bool(device.compliant),
+            Self::CertificateValid => {
+                let now = chrono::Utc::now();
+                Value::Bool(device.certificates.iter().any(|c| c.valid_from <= now && now < c.valid_until))
+            }
+            Self::Os => Value::Text(device.os.clone()),
+            Self::OsVersion => Value::Text(device.os_version.clone()),
+            Self::DeviceType => Value::Text(DEVICE_TYPES[device.device_type as usize].to_string()),
+            Self::Antivirus => Value::List(posture.antivirus_products.clone()),
+            Self::PatchAgeDays => match posture.os_patch_age_days {
+                Some(days) => Value::Number(days as f64),
+                None => Value::Missing,
+            },
+            Self::Attribute(key) => match posture.attributes.get(key) {
+                Some(value) => Value::Text(value.clone()),
+                None => Value::Missing,
+            },
+        }
+    }
+}
+
+enum Value {
+    Bool(bool),
+    Text(String),
+    Number(f64),
+    List(Vec<String>),
+    Missing,
+}
+
+// =============================================================================
+// Expression Tree
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn holds(self, ordering: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match self {
+            Self::Eq => ordering == Equal,
+            Self::Ne => ordering != Equal,
+            Self::Lt => ordering == Less,
+            Self::Le => ordering != Greater,
+            Self::Gt => ordering == Greater,
+            Self::Ge => ordering != Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Bool(bool),
+    Text(String),
+    Number(f64),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    /// Boolean fact on its own
+    Check(Fact),
+    Compare { fact: Fact, op: Op, value: Literal },
+    In { fact: Fact, values: Vec<String> },
+    Contains { fact: Fact, value: String },
+}
+
+impl Node {
+    fn evaluate(&self, device: &Device) -> bool {
+        match self {
+            Self::And(nodes) => nodes.iter().all(|n| n.evaluate(device)),
+            Self::Or(nodes) => nodes.iter().any(|n| n.evaluate(device)),
+            Self::Not(node) => !node.evaluate(device),
+            Self::Check(fact) => matches!(fact.value(device), Value::Bool(true)),
+            Self::Compare { fact, op, value } => match (fact.value(device), value) {
+                (Value::Bool(actual), Literal::Bool(expected)) => op.holds(actual.cmp(expected)),
+                (Value::Text(actual), Literal::Text(expected)) if fact.kind() == Kind::Version => {
+                    op.holds(version_compare(&actual, expected).cmp(&0))
+                }
+                (Value::Text(actual), Literal::Text(expected)) => {
+                    op.holds(actual.to_lowercase().cmp(&expected.to_lowercase()))
+                }
+                (Value::Number(actual), Literal::Number(expected)) => {
+                    actual.partial_cmp(expected).is_some_and(|o| op.holds(o))
+                }
+                _ => false,
+            },
+            Self::In { fact, values } => match fact.value(device) {
+                Value::Text(actual) => values.iter().any(|v| v.eq_ignore_ascii_case(&actual)),
+                Value::List(actual) => actual.iter().any(|a| values.iter().any(|v| v.eq_ignore_ascii_case(a))),
+                _ => false,
+            },
+            Self::Contains { fact, value } => match fact.value(device) {
+                Value::List(actual) => actual.iter().any(|a| a.eq_ignore_ascii_case(value)),
+                _ => false,
+            },
+        }
+    }
+}
+
+// =============================================================================
+// Parser
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    List(Vec<String>),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Self::Word(w) => format!("'{}'", w),
+            Self::Quoted(q) => format!("\"{}\"", q),
+            Self::List(_) => "list".to_string(),
+            Self::Op(op) => format!("{:?}", op),
+            Self::Open => "'('".to_string(),
+            Self::Close => "')'".to_string(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek().is_some_and(|t| t.is_keyword(keyword)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![self.and()?];
+        while self.eat_keyword("or") {
+            nodes.push(self.and()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::Or(nodes) })
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![self.unary()?];
+        while self.eat_keyword("and") {
+            nodes.push(self.unary()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::And(nodes) })
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat_keyword("not") {
+            return Ok(Node::Not(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let node = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(node),
+                    Some(token) => Err(format!("expected ')', found {}", token.describe())),
+                    None => Err("missing ')'".to_string()),
+                }
+            }
+            Some(Token::Word(name)) if !is_reserved(&name) => self.test(&name, Fact::parse(&name)?),
+            Some(token) => Err(format!("expected a posture fact, found {}", token.describe())),
+            None => Err("expression ends early".to_string()),
+        }
+    }
+
+    fn test(&mut self, name: &str, fact: Fact) -> Result<Node, String> {
+        let kind = fact.kind();
+
+        if self.eat_keyword("in") {
+            let values = match self.next() {
+                Some(Token::List(values)) => values,
+                _ => return Err(format!("'in' needs a list, e.g. {} in [a, b]", name)),
+            };
+            if !matches!(kind, Kind::Text | Kind::List) {
+                return Err(format!("{} cannot be matched against a list", name));
+            }
+            return Ok(Node::In { fact, values });
+        }
+
+        if self.eat_keyword("contains") {
+            if kind != Kind::List {
+                return Err(format!("{} is not a list", name));
+            }
+            return Ok(Node::Contains { fact, value: self.scalar()? });
+        }
+
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ if kind == Kind::Bool => return Ok(Node::Check(fact)),
+            _ => return Err(format!("{} needs a comparison", name)),
+        };
+        self.pos += 1;
+
+        let raw = self.scalar()?;
+        let value = match kind {
+            Kind::Bool => match raw.to_ascii_lowercase().as_str() {
+                "true" => Literal::Bool(true),
+                "false" => Literal::Bool(false),
+                _ => return Err(format!("{} compares with true or false, not '{}'", name, raw)),
+            },
+            Kind::Number => Literal::Number(
+                raw.parse().map_err(|_| format!("{} compares with a number, not '{}'", name, raw))?,
+            ),
+            Kind::Version => {
+                if raw.split('.').any(|part| part.parse::<u32>().is_err()) {
+                    return Err(format!("'{}' is not a dotted version", raw));
+                }
+                Literal::Text(raw)
+            }
+            Kind::Text => Literal::Text(raw),
+            Kind::List => return Err(format!("{} matches with 'in' or 'contains'", name)),
+        };
+
+        if matches!(kind, Kind::Bool | Kind::Text) && !matches!(op, Op::Eq | Op::Ne) {
+            return Err(format!("{} only supports == and !=", name));
+        }
+        if kind == Kind::Text && matches!(fact, Fact::DeviceType) {
+            if let Literal::Text(name) = &value {
+                if !DEVICE_TYPES.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+                    return Err(format!("unknown device type '{}'", name));
+                }
+            }
+        }
+
+        Ok(Node::Compare { fact, op, value })
+    }
+
+    fn scalar(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(w)) if !is_reserved(&w) => Ok(w),
+            Some(Token::Quoted(q)) => Ok(q),
+            Some(token) => Err(format!("expected a value, found {}", token.describe())),
+            None => Err("expression ends early".to_string()),
+        }
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    ["and", "or", "not", "in", "contains"].iter().any(|k| word.eq_ignore_ascii_case(k))
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '\'' | '"' => {
+                chars.next();
+                tokens.push(Token::Quoted(read_quoted(&mut chars, c)?));
+            }
+            '[' => {
+                chars.next();
+                tokens.push(Token::List(read_list(&mut chars)?));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                let op = match (c, eq) {
+                    ('=', true) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return Err(format!("unexpected '{}'", c)),
+                };
+                tokens.push(Token::Op(op));
+            }
+            c if is_word_char(c) => tokens.push(Token::Word(read_word(&mut chars))),
+            c => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '[' | ']' | ',' | '\'' | '"' | '=' | '!' | '<' | '>')
+}
+
+fn read_word(chars: &mut Chars) -> String {
+    let mut word = String::new();
+    while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+        word.push(c);
+    }
+    word
+}
+
+fn read_quoted(chars: &mut Chars, quote: char) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('\\') => match chars.next() {
+                Some(escaped) => value.push(escaped),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) if c == quote => return Ok(value),
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+fn read_list(chars: &mut Chars) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.peek().copied() {
+            None => return Err("unterminated list".to_string()),
+            Some(']') => {
+                chars.next();
+                return Ok(values);
+            }
+            Some(',') => {
+                chars.next();
+            }
+            Some(quote @ ('\'' | '"')) => {
+                chars.next();
+                values.push(read_quoted(chars, quote)?);
+            }
+            Some(_) => {
+                let mut value = String::new();
+                while let Some(c) = chars.next_if(|c| !matches!(c, ',' | ']')) {
+                    value.push(c);
+                }
+                values.push(value.trim().to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Patched macOS 14.1 laptop running CrowdStrike
+    fn device() -> Device {
+        serde_json::from_value(serde_json::json!({
+            "id": "d1",
+            "name": "laptop-7",
+            "device_type": "Laptop",
+            "os": "macOS",
+            "os_version": "14.1",
+            "managed": true,
+            "compliant": true,
+            "trust_level": "High",
+            "posture": {
+                "firewall_enabled": true,
+                "antivirus_running": true,
+                "disk_encrypted": true,
+                "os_patched": false,
+                "screen_lock_enabled": true,
+                "jailbroken": false,
+                "last_checked": chrono::Utc::now(),
+                "antivirus_products": ["CrowdStrike Falcon", "XProtect"],
+                "os_patch_age_days": 20,
+                "attributes": { "exception": "approved" },
+            },
+            "certificates": [],
+            "last_seen": chrono::Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    fn eval(source: &str) -> bool {
+        PostureExpr::parse(source).unwrap().evaluate(&device())
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        // AND binds tighter than OR, NOT tighter than both
+        assert!(eval("os_patched and jailbroken or disk_encrypted"));
+        assert!(!eval("os_patched AND (jailbroken OR disk_encrypted)"));
+        assert!(eval("not os_patched and not jailbroken"));
+        assert!(!eval("NOT (os_patched OR disk_encrypted)"));
+        assert!(eval("firewall == true AND screen_lock != false"));
+        assert_eq!(PostureExpr::parse("  managed  ").unwrap().source(), "managed");
+    }
+
+    #[test]
+    fn test_typed_comparisons() {
+        assert!(eval("os == MACOS"));
+        assert!(eval("os in [Windows, 'macOS']"));
+        assert!(eval("device_type == laptop"));
+        // Versions compare numerically, not as text
+        assert!(eval("os_version >= 14.0 AND os_version < 14.10"));
+        assert!(!eval("os != macOS OR os_version >= 14.2"));
+        assert!(eval("patch_age_days <= 30 AND patch_age_days > 14"));
+        assert!(eval("antivirus in [\"CrowdStrike Falcon\", Defender]"));
+        assert!(eval("antivirus contains xprotect"));
+        assert!(!eval("antivirus contains Defender"));
+        assert!(eval("attr.exception == Approved"));
+    }
+
+    #[test]
+    fn test_missing_facts_fail_every_comparison() {
+        let mut device = device();
+        device.posture.os_patch_age_days = None;
+        for source in ["patch_age_days <= 30", "patch_age_days > 30", "attr.owner == it", "attr.owner != it"] {
+            assert!(!PostureExpr::parse(source).unwrap().evaluate(&device), "{}", source);
+        }
+        // No certificates reported
+        assert!(!PostureExpr::parse("certificate_valid").unwrap().evaluate(&device));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |source: &str| PostureExpr::parse(source).unwrap_err();
+
+        assert_eq!(error(""), "empty expression");
+        assert_eq!(error("os_patched AND"), "expression ends early");
+        assert_eq!(error("(os_patched"), "missing ')'");
+        assert_eq!(error("os_patched disk_encrypted"), "unexpected 'disk_encrypted'");
+        assert_eq!(error("uptime > 3"), "unknown posture fact 'uptime'");
+        assert_eq!(error("attr. == x"), "attribute name missing after 'attr.'");
+        assert_eq!(error("os"), "os needs a comparison");
+        assert_eq!(error("os > Linux"), "os only supports == and !=");
+        assert_eq!(error("jailbroken == maybe"), "jailbroken compares with true or false, not 'maybe'");
+        assert_eq!(error("patch_age_days < soon"), "patch_age_days compares with a number, not 'soon'");
+        assert_eq!(error("os_version >= 14.x"), "'14.x' is not a dotted version");
+        assert_eq!(error("device_type == Phone"), "unknown device type 'Phone'");
+        assert_eq!(error("disk_encrypted in [true]"), "disk_encrypted cannot be matched against a list");
+        assert_eq!(error("os contains mac"), "os is not a list");
+        assert_eq!(error("antivirus == Defender"), "antivirus matches with 'in' or 'contains'");
+        assert_eq!(error("os in Windows"), "'in' needs a list, e.g. os in [a, b]");
+        assert_eq!(error("os == 'macOS"), "unterminated string");
+        assert_eq!(error("os in [a, b"), "unterminated list");
+        assert_eq!(error("os = macOS"), "unexpected '='");
+    }
+}
@@ -1,8 +1,18 @@
 //! Device Posture Assessment
 //!
 //! Comprehensive device posture checking and compliance.
+//!
+//! - [`lang`]: posture expressions over reported device facts
+//! - [`policy`]: versioned per-tenant posture policies and scoring
+
+pub mod lang;
+pub mod policy;
+
+pub use lang::PostureExpr;
+pub use policy::{PostureEvaluation, PosturePolicyEngine, RemediationHint};
 
 use crate::trust_engine::{EnhancedDevicePosture, ManagementStatus};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 /// Comprehensive device posture assessor
@@ -36,7 +46,7 @@ pub enum PostureRequirement {
     SecureBoot,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RuleSeverity {
     Critical,
     High,
@@ -0,0 +1,489 @@
+//! Tenant Posture Policies
+//!
+//! Each tenant publishes a posture policy: a list of rules, each a
+//! [`PostureExpr`] with a severity and a remediation hint.
+//!
+//! ```yaml
+//! name: corporate-laptops
+//! rules:
+//!   - id: patched
+//!     require: os_patched OR patch_age_days <= 14
+//!     severity: High
+//!     remediation: Install pending OS updates
+//!   - id: edr
+//!     require: antivirus in [CrowdStrike, Defender]
+//!     severity: Critical
+//!     remediation: Install the CrowdStrike Falcon sensor
+//! ```
+//!
+//! Publishing compiles the document and stores it as the tenant's next
+//! version. Earlier versions are kept, so a bad policy can be rolled back by
+//! re-activating one of them.
+//!
+//! Evaluation weighs every rule by severity (Critical 10, High 5, Medium 2,
+//! Low 1) and scores the device 0-100 on the weight of the rules it passes.
+//! The score maps onto a [`TrustLevel`] through the policy's thresholds, and
+//! any failed Critical rule makes the device untrusted whatever its score.
+//! Failed rules come back as remediation hints for the client to show.
+
+use super::lang::PostureExpr;
+use super::RuleSeverity;
+use crate::{AccessRequest, Device, TrustLevel};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Identity attribute naming the tenant whose policy applies
+pub const TENANT_ATTRIBUTE: &str = "tenant_id";
+/// Tenant whose policy applies to identities without one
+pub const DEFAULT_TENANT: &str = "default";
+
+#[derive(Debug)]
+pub enum PostureError {
+    /// Document is not valid YAML or does not match the schema
+    Syntax(String),
+    DuplicateRule(String),
+    /// A rule's expression failed to compile
+    Rule { rule: String, message: String },
+    UnknownTenant(String),
+    UnknownVersion { tenant_id: String, version: u32 },
+}
+
+impl std::fmt::Display for PostureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(e) => write!(f, "Posture policy invalid: {}", e),
+            Self::DuplicateRule(id) => write!(f, "Duplicate posture rule id: {}", id),
+            Self::Rule { rule, message } => write!(f, "Posture rule {}: {}", rule, message),
+            Self::UnknownTenant(tenant) => write!(f, "No posture policy for tenant {}", tenant),
+            Self::UnknownVersion { tenant_id, version } => {
+                write!(f, "No posture policy version {} for tenant {}", version, tenant_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PostureError {}
+
+// =============================================================================
+// Policy Documents
+// =============================================================================
+
+/// Posture policy as authored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PosturePolicyDocument {
+    #[serde(default)]
+    pub name: String,
+    pub rules: Vec<PostureRuleDocument>,
+    #[serde(default)]
+    pub thresholds: TrustThresholds,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostureRuleDocument {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// Posture expression the device must satisfy
+    pub require: String,
+    pub severity: RuleSeverity,
+    /// Shown to the user when the rule fails
+    pub remediation: String,
+}
+
+/// Lowest score for each trust level
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TrustThresholds {
+    pub full: f64,
+    pub high: f64,
+    pub medium: f64,
+    pub low: f64,
+}
+
+impl Default for TrustThresholds {
+    fn default() -> Self {
+        Self { full: 95.0, high: 80.0, medium: 60.0, low: 40.0 }
+    }
+}
+
+impl TrustThresholds {
+    fn trust_level(&self, score: f64) -> TrustLevel {
+        if score >= self.full {
+            TrustLevel::Full
+        } else if score >= self.high {
+            TrustLevel::High
+        } else if score >= self.medium {
+            TrustLevel::Medium
+        } else if score >= self.low {
+            TrustLevel::Low
+        } else {
+            TrustLevel::Untrusted
+        }
+    }
+}
+
+impl RuleSeverity {
+    /// Weight of a rule in the posture score
+    pub fn weight(self) -> f64 {
+        match self {
+            Self::Critical => 10.0,
+            Self::High => 5.0,
+            Self::Medium => 2.0,
+            Self::Low => 1.0,
+        }
+    }
+}
+
+// =============================================================================
+// Compiled Policies
+// =============================================================================
+
+/// A published, compiled version of a tenant's policy
+#[derive(Debug)]
+pub struct CompiledPosturePolicy {
+    pub tenant_id: String,
+    pub version: u32,
+    pub published_at: DateTime<Utc>,
+    pub published_by: String,
+    pub document: PosturePolicyDocument,
+    rules: Vec<(PostureRuleDocument, PostureExpr)>,
+}
+
+impl CompiledPosturePolicy {
+    fn compile(
+        tenant_id: &str,
+        version: u32,
+        document: PosturePolicyDocument,
+        published_by: &str,
+    ) -> Result<Self, PostureError> {
+        let mut seen = HashSet::new();
+        let mut rules = Vec::with_capacity(document.rules.len());
+        for rule in &document.rules {
+            if !seen.insert(rule.id.as_str()) {
+                return Err(PostureError::DuplicateRule(rule.id.clone()));
+            }
+            let expr = PostureExpr::parse(&rule.require)
+                .map_err(|message| PostureError::Rule { rule: rule.id.clone(), message })?;
+            rules.push((rule.clone(), expr));
+        }
+
+        Ok(Self {
+            tenant_id: tenant_id.to_string(),
+            version,
+            published_at: Utc::now(),
+            published_by: published_by.to_string(),
+            document,
+            rules,
+        })
+    }
+
+    /// Score a device against every rule
+    pub fn evaluate(&self, device: &Device) -> PostureEvaluation {
+        let mut total = 0.0;
+        let mut passed = 0.0;
+        let mut failures = Vec::new();
+
+        for (rule, expr) in &self.rules {
+            let weight = rule.severity.weight();
+            total += weight;
+            if expr.evaluate(device) {
+                passed += weight;
+            } else {
+                failures.push(RemediationHint {
+                    rule_id: rule.id.clone(),
+                    severity: rule.severity,
+                    requirement: expr.source().to_string(),
+                    remediation: rule.remediation.clone(),
+                });
+            }
+        }
+        // Worst first, so clients can show the most pressing fix on top
+        failures.sort_by_key(|f| f.severity);
+
+        let score = if total > 0.0 { 100.0 * passed / total } else { 100.0 };
+        let critical = failures.iter().any(|f| f.severity == RuleSeverity::Critical);
+        let trust_level = if critical {
+            TrustLevel::Untrusted
+        } else {
+            self.document.thresholds.trust_level(score)
+        };
+
+        PostureEvaluation {
+            tenant_id: self.tenant_id.clone(),
+            policy_version: self.version,
+            score,
+            trust_level,
+            compliant: failures.is_empty(),
+            failures,
+            evaluated_at: Utc::now(),
+        }
+    }
+}
+
+/// A failed rule and how to fix it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemediationHint {
+    pub rule_id: String,
+    pub severity: RuleSeverity,
+    /// Expression the device did not satisfy
+    pub requirement: String,
+    pub remediation: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostureEvaluation {
+    pub tenant_id: String,
+    pub policy_version: u32,
+    /// Severity-weighted share of rules passed, 0-100
+    pub score: f64,
+    pub trust_level: TrustLevel,
+    /// Every rule passed
+    pub compliant: bool,
+    /// Failed rules, most severe first
+    pub failures: Vec<RemediationHint>,
+    pub evaluated_at: DateTime<Utc>,
+}
+
+impl PostureEvaluation {
+    /// Posture too poor for any access
+    pub fn blocked(&self) -> bool {
+        self.trust_level < TrustLevel::Low
+    }
+}
+
+/// Published version summary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyVersion {
+    pub version: u32,
+    pub name: String,
+    pub rules: usize,
+    pub published_at: DateTime<Utc>,
+    pub published_by: String,
+    pub active: bool,
+}
+
+// =============================================================================
+// Policy Engine
+// =============================================================================
+
+#[derive(Default)]
+struct TenantPolicies {
+    versions: Vec<Arc<CompiledPosturePolicy>>,
+    active: Option<u32>,
+}
+
+/// Versioned posture policies per tenant
+#[derive(Default)]
+pub struct PosturePolicyEngine {
+    tenants: dashmap::DashMap<String, TenantPolicies>,
+}
+
+impl PosturePolicyEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile a policy and make it the tenant's active version
+    pub fn publish(
+        &self,
+        tenant_id: &str,
+        document: PosturePolicyDocument,
+        published_by: &str,
+    ) -> Result<u32, PostureError> {
+        let mut tenant = self.tenants.entry(tenant_id.to_string()).or_default();
+        let version = tenant.versions.last().map_or(1, |p| p.version + 1);
+        let policy = CompiledPosturePolicy::compile(tenant_id, version, document, published_by)?;
+
+        tenant.versions.push(Arc::new(policy));
+        tenant.active = Some(version);
+        tracing::info!("Posture policy v{} published for tenant {} by {}", version, tenant_id, published_by);
+        Ok(version)
+    }
+
+    /// Publish a policy written in YAML
+    pub fn publish_yaml(&self, tenant_id: &str, yaml: &str, published_by: &str) -> Result<u32, PostureError> {
+        let document = serde_yaml::from_str(yaml).map_err(|e| PostureError::Syntax(e.to_string()))?;
+        self.publish(tenant_id, document, published_by)
+    }
+
+    /// Make an earlier (or later) version active again
+    pub fn activate(&self, tenant_id: &str, version: u32) -> Result<(), PostureError> {
+        let mut tenant = self.tenants.get_mut(tenant_id)
+            .ok_or_else(|| PostureError::UnknownTenant(tenant_id.to_string()))?;
+        if !tenant.versions.iter().any(|p| p.version == version) {
+            return Err(PostureError::UnknownVersion { tenant_id: tenant_id.to_string(), version });
+        }
+        tenant.active = Some(version);
+        tracing::info!("Posture policy v{} activated for tenant {}", version, tenant_id);
+        Ok(())
+    }
+
+    /// Stop enforcing posture policy for a tenant; versions are kept
+    pub fn deactivate(&self, tenant_id: &str) -> bool {
+        self.tenants.get_mut(tenant_id)
+            .is_some_and(|mut t| t.active.take().is_some())
+    }
+
+    pub fn active(&self, tenant_id: &str) -> Option<Arc<CompiledPosturePolicy>> {
+        let tenant = self.tenants.get(tenant_id)?;
+        let active = tenant.active?;
+        tenant.versions.iter().find(|p| p.version == active).cloned()
+    }
+
+    pub fn get(&self, tenant_id: &str, version: u32) -> Option<Arc<CompiledPosturePolicy>> {
+        self.tenants.get(tenant_id)?
+            .versions.iter().find(|p| p.version == version).cloned()
+    }
+
+    /// Published versions of a tenant's policy, oldest first
+    pub fn versions(&self, tenant_id: &str) -> Vec<PolicyVersion> {
+        let Some(tenant) = self.tenants.get(tenant_id) else {
+            return Vec::new();
+        };
+        tenant.versions.iter()
+            .map(|p| PolicyVersion {
+                version: p.version,
+                name: p.document.name.clone(),
+                rules: p.rules.len(),
+                published_at: p.published_at,
+                published_by: p.published_by.clone(),
+                active: tenant.active == Some(p.version),
+            })
+            .collect()
+    }
+
+    /// Policy governing a request: its tenant's, else the default tenant's
+    pub fn policy_for(&self, request: &AccessRequest) -> Option<Arc<CompiledPosturePolicy>> {
+        request.identity.attributes.get(TENANT_ATTRIBUTE)
+            .and_then(|tenant| self.active(tenant))
+            .or_else(|| self.active(DEFAULT_TENANT))
+    }
+
+    /// Evaluate a request's device; `None` when no policy applies
+    pub fn evaluate(&self, request: &AccessRequest) -> Option<PostureEvaluation> {
+        let evaluation = self.policy_for(request)?.evaluate(&request.device);
+        if !evaluation.compliant {
+            tracing::debug!(
+                "Device {} fails {} posture rule(s) of tenant {} v{} (score {:.0})",
+                request.device.id, evaluation.failures.len(),
+                evaluation.tenant_id, evaluation.policy_version, evaluation.score
+            );
+        }
+        Some(evaluation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::testing::RequestFixture;
+
+    const POLICY: &str = r#"
+name: corporate-laptops
+rules:
+  - id: edr
+    require: antivirus_running
+    severity: Critical
+    remediation: Start the EDR agent
+  - id: patched
+    require: os_patched
+    severity: High
+    remediation: Install pending OS updates
+  - id: firewall
+    require: firewall_enabled
+    severity: Medium
+    remediation: Turn on the firewall
+  - id: screen-lock
+    require: screen_lock
+    severity: Low
+    remediation: Enable the screen lock
+"#;
+
+    fn request(yaml: &str) -> AccessRequest {
+        serde_yaml::from_str::<RequestFixture>(yaml).unwrap().build().unwrap()
+    }
+
+    fn evaluate(engine: &PosturePolicyEngine, posture: &str) -> PostureEvaluation {
+        engine.evaluate(&request(&format!("device: {{ posture: {} }}", posture))).unwrap()
+    }
+
+    #[test]
+    fn test_weighted_score_and_trust_level() {
+        let engine = PosturePolicyEngine::new();
+        engine.publish_yaml(DEFAULT_TENANT, POLICY, "admin").unwrap();
+
+        let healthy = evaluate(&engine, "{}");
+        assert_eq!((healthy.score, healthy.trust_level, healthy.compliant), (100.0, TrustLevel::Full, true));
+
+        // Medium and Low rules failed: 15 of 18 points
+        let lax = evaluate(&engine, "{ firewall_enabled: false, screen_lock_enabled: false }");
+        assert!((lax.score - 100.0 * 15.0 / 18.0).abs() < 1e-9);
+        assert_eq!(lax.trust_level, TrustLevel::High);
+        let failed: Vec<&str> = lax.failures.iter().map(|f| f.rule_id.as_str()).collect();
+        assert_eq!(failed, vec!["firewall", "screen-lock"]);
+        assert_eq!(lax.failures[0].requirement, "firewall_enabled");
+        assert_eq!(lax.failures[0].remediation, "Turn on the firewall");
+
+        let unpatched = evaluate(&engine, "{ os_patched: false }");
+        assert_eq!(unpatched.trust_level, TrustLevel::Medium);
+        assert!(!unpatched.blocked());
+
+        // A failed Critical rule overrides the score
+        let no_edr = evaluate(&engine, "{ antivirus_running: false, firewall_enabled: false }");
+        assert_eq!(no_edr.failures[0].severity, RuleSeverity::Critical);
+        assert_eq!(no_edr.trust_level, TrustLevel::Untrusted);
+        assert!(no_edr.blocked());
+    }
+
+    #[test]
+    fn test_publish_rejects_invalid_policies() {
+        let engine = PosturePolicyEngine::new();
+
+        assert!(matches!(engine.publish_yaml("acme", "rules: nope", "admin"), Err(PostureError::Syntax(_))));
+        let duplicate = format!("{}  - id: edr\n    require: managed\n    severity: Low\n    remediation: x\n", POLICY);
+        assert!(matches!(engine.publish_yaml("acme", &duplicate, "admin"), Err(PostureError::DuplicateRule(id)) if id == "edr"));
+        let broken = POLICY.replace("require: os_patched", "require: os_patched AND");
+        match engine.publish_yaml("acme", &broken, "admin") {
+            Err(PostureError::Rule { rule, message }) => {
+                assert_eq!(rule, "patched");
+                assert_eq!(message, "expression ends early");
+            }
+            other => panic!("expected a rule error, got {:?}", other.map(|_| ())),
+        }
+        // Failed publishes don't use up versions
+        assert_eq!(engine.publish_yaml("acme", POLICY, "admin").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_versions_and_tenant_selection() {
+        let engine = PosturePolicyEngine::new();
+        let strict = POLICY.replace("corporate-laptops", "strict").replace("severity: Low", "severity: Critical");
+        engine.publish_yaml("acme", POLICY, "alice").unwrap();
+        assert_eq!(engine.publish_yaml("acme", &strict, "bob").unwrap(), 2);
+
+        let acme = "{ identity: { attributes: { tenant_id: acme } }, device: { posture: { screen_lock_enabled: false } } }";
+        assert_eq!(engine.evaluate(&request(acme)).unwrap().trust_level, TrustLevel::Untrusted);
+
+        // Roll back to the first version
+        engine.activate("acme", 1).unwrap();
+        let versions = engine.versions("acme");
+        assert_eq!(versions.iter().map(|v| (v.version, v.active)).collect::<Vec<_>>(), vec![(1, true), (2, false)]);
+        assert_eq!(versions[1].name, "strict");
+        assert_eq!(engine.evaluate(&request(acme)).unwrap().policy_version, 1);
+
+        assert!(matches!(engine.activate("acme", 3), Err(PostureError::UnknownVersion { version: 3, .. })));
+        assert!(matches!(engine.activate("globex", 1), Err(PostureError::UnknownTenant(_))));
+
+        // Other tenants fall back to the default tenant's policy, if any
+        let globex = "{ identity: { attributes: { tenant_id: globex } } }";
+        assert!(engine.evaluate(&request(globex)).is_none());
+        engine.publish_yaml(DEFAULT_TENANT, POLICY, "alice").unwrap();
+        assert_eq!(engine.evaluate(&request(globex)).unwrap().tenant_id, DEFAULT_TENANT);
+
+        assert!(engine.deactivate("acme"));
+        assert!(!engine.deactivate("acme"));
+        assert_eq!(engine.evaluate(&request(acme)).unwrap().tenant_id, DEFAULT_TENANT);
+        assert_eq!(engine.get("acme", 2).unwrap().published_by, "bob");
+    }
+}
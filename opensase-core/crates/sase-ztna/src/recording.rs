@@ -34,7 +34,7 @@ pub struct RecordingMetadata {
     pub files_transferred: u32,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RecordingType {
     Full,           // Everything
     KeystrokeOnly,  // Just keystrokes
@@ -209,6 +209,15 @@ impl EnhancedSessionRecorder {
         }
     }
     
+    /// Note an application used during the recording
+    pub fn note_app(&self, recording_id: &str, app_id: &str) {
+        if let Some(mut recording) = self.recordings.get_mut(recording_id) {
+            if !recording.metadata.apps_accessed.iter().any(|a| a == app_id) {
+                recording.metadata.apps_accessed.push(app_id.to_string());
+            }
+        }
+    }
+    
     /// Record keystroke
     pub async fn record_keystroke(
         &self,
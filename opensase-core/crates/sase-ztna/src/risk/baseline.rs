@@ -0,0 +1,252 @@
+//! Behavioural Baselines
+//!
+//! Per-user record of where, when and from what a user normally connects:
+//! countries, origin ASNs, hours of day and devices. Every feature carries an
+//! exponentially decayed weight, so a relocation or a new laptop becomes
+//! "normal" over a few weeks and a one-off trip fades out again.
+//!
+//! Each access is checked against the baseline *before* it is folded in,
+//! producing `NewDevice`, `NewLocation` and `UnusualTime` signals for the
+//! risk engine. Travel speed is the geo-velocity engine's concern.
+
+use crate::{AccessRequest, RiskSeverity, RiskSignal, RiskSignalType};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Weights below this are dropped when decaying
+const PRUNE_WEIGHT: f64 = 0.01;
+
+#[derive(Debug, Clone)]
+pub struct BaselineConfig {
+    /// Time for a feature's weight to halve without reinforcement
+    pub half_life: Duration,
+    /// Observations before novelty signals are raised
+    pub learning_observations: u64,
+    /// Share of a user's decayed weight a country, ASN or device needs to
+    /// count as familiar
+    pub familiar_share: f64,
+    /// Share of weight within ±1 hour below which access is unusual
+    pub unusual_hour_share: f64,
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            half_life: Duration::days(14),
+            learning_observations: 10,
+            familiar_share: 0.02,
+            unusual_hour_share: 0.02,
+        }
+    }
+}
+
+/// Decayed behavioural profile for one user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserBaseline {
+    pub countries: HashMap<String, f64>,
+    pub asns: HashMap<u32, f64>,
+    pub devices: HashMap<String, f64>,
+    /// Weight per UTC hour of day
+    pub hours: [f64; 24],
+    /// Total decayed weight of all observations
+    pub weight: f64,
+    /// Observations ever recorded (not decayed)
+    pub observations: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UserBaseline {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            countries: HashMap::new(),
+            asns: HashMap::new(),
+            devices: HashMap::new(),
+            hours: [0.0; 24],
+            weight: 0.0,
+            observations: 0,
+            updated_at: now,
+        }
+    }
+
+    /// Age all weights to `now`
+    fn decay_to(&mut self, now: DateTime<Utc>, half_life: Duration) {
+        let elapsed = (now - self.updated_at).num_seconds();
+        if elapsed <= 0 {
+            return;
+        }
+
+        let half_life = half_life.num_seconds().max(1) as f64;
+        let factor = 0.5f64.powf(elapsed as f64 / half_life);
+
+        for weight in self.countries.values_mut()
+            .chain(self.devices.values_mut())
+            .chain(self.asns.values_mut())
+            .chain(self.hours.iter_mut())
+        {
+            *weight *= factor;
+        }
+        self.countries.retain(|_, w| *w >= PRUNE_WEIGHT);
+        self.devices.retain(|_, w| *w >= PRUNE_WEIGHT);
+        self.asns.retain(|_, w| *w >= PRUNE_WEIGHT);
+        self.weight *= factor;
+        self.updated_at = now;
+    }
+
+    fn share<K: std::hash::Hash + Eq>(&self, map: &HashMap<K, f64>, key: &K) -> f64 {
+        if self.weight <= 0.0 {
+            return 0.0;
+        }
+        map.get(key).copied().unwrap_or(0.0) / self.weight
+    }
+
+    fn hour_share(&self, hour: usize) -> f64 {
+        if self.weight <= 0.0 {
+            return 0.0;
+        }
+        let around = [(hour + 23) % 24, hour, (hour + 1) % 24];
+        around.iter().map(|h| self.hours[*h]).sum::<f64>() / self.weight
+    }
+
+    fn record(&mut self, request: &AccessRequest, at: DateTime<Utc>) {
+        let context = &request.context;
+
+        if let Some(geo) = &context.geo_location {
+            *self.countries.entry(geo.country.clone()).or_insert(0.0) += 1.0;
+            if let Some(asn) = geo.asn {
+                *self.asns.entry(asn).or_insert(0.0) += 1.0;
+            }
+        }
+        *self.devices.entry(request.device.id.clone()).or_insert(0.0) += 1.0;
+        self.hours[at.hour() as usize] += 1.0;
+        self.weight += 1.0;
+        self.observations += 1;
+    }
+}
+
+/// Baseline store and signal generator
+pub struct BehaviorBaselines {
+    baselines: dashmap::DashMap<String, UserBaseline>,
+    config: BaselineConfig,
+}
+
+impl BehaviorBaselines {
+    pub fn new(config: BaselineConfig) -> Self {
+        Self {
+            baselines: dashmap::DashMap::new(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &BaselineConfig {
+        &self.config
+    }
+
+    /// Check an access against the user's baseline, then learn from it
+    pub fn observe(&self, request: &AccessRequest) -> Vec<RiskSignal> {
+        let at = request.context.time_of_access;
+        let mut baseline = self.baselines.entry(request.identity.user_id.clone())
+            .or_insert_with(|| UserBaseline::new(at));
+        baseline.decay_to(at, self.config.half_life);
+
+        let signals = self.check(&baseline, request);
+        baseline.record(request, at);
+
+        for signal in &signals {
+            tracing::debug!(
+                "Baseline signal for {}: {:?} ({})",
+                request.identity.user_id, signal.signal_type, signal.description
+            );
+        }
+        signals
+    }
+
+    fn check(&self, baseline: &UserBaseline, request: &AccessRequest) -> Vec<RiskSignal> {
+        let mut signals = Vec::new();
+        let context = &request.context;
+        let now = Utc::now();
+
+        // Novelty needs enough history to know what is normal
+        if baseline.observations < self.config.learning_observations {
+            return signals;
+        }
+
+        if baseline.share(&baseline.devices, &request.device.id) < self.config.familiar_share {
+            signals.push(RiskSignal {
+                signal_type: RiskSignalType::NewDevice,
+                severity: RiskSeverity::Medium,
+                description: format!("Access from unfamiliar device: {}", request.device.name),
+                detected_at: now,
+            });
+        }
+
+        if let Some(geo) = &context.geo_location {
+            if baseline.share(&baseline.countries, &geo.country) < self.config.familiar_share {
+                signals.push(RiskSignal {
+                    signal_type: RiskSignalType::NewLocation,
+                    severity: RiskSeverity::Medium,
+                    description: format!("Access from unfamiliar country: {}", geo.country),
+                    detected_at: now,
+                });
+            } else if let Some(asn) = geo.asn {
+                if baseline.share(&baseline.asns, &asn) < self.config.familiar_share {
+                    signals.push(RiskSignal {
+                        signal_type: RiskSignalType::NewLocation,
+                        severity: RiskSeverity::Low,
+                        description: format!("Access from unfamiliar network AS{}", asn),
+                        detected_at: now,
+                    });
+                }
+            }
+        }
+
+        let hour = context.time_of_access.hour() as usize;
+        if baseline.hour_share(hour) < self.config.unusual_hour_share {
+            signals.push(RiskSignal {
+                signal_type: RiskSignalType::UnusualTime,
+                severity: RiskSeverity::Low,
+                description: format!("Access at unusual hour: {:02}:00 UTC", hour),
+                detected_at: now,
+            });
+        }
+
+        signals
+    }
+
+    /// Snapshot of a user's baseline
+    pub fn baseline(&self, user_id: &str) -> Option<UserBaseline> {
+        self.baselines.get(user_id).map(|b| b.clone())
+    }
+
+    /// Seed a baseline, e.g. restored from storage
+    pub fn restore(&self, user_id: &str, baseline: UserBaseline) {
+        self.baselines.insert(user_id.to_string(), baseline);
+    }
+
+    /// Forget a user's baseline (e.g. after confirmed account compromise)
+    pub fn reset(&self, user_id: &str) {
+        self.baselines.remove(user_id);
+    }
+
+    /// Drop baselines with no activity for `max_idle`
+    pub fn prune_idle(&self, max_idle: Duration) -> usize {
+        let cutoff = Utc::now() - max_idle;
+        let before = self.baselines.len();
+        self.baselines.retain(|_, b| b.updated_at >= cutoff);
+        before - self.baselines.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.baselines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.baselines.is_empty()
+    }
+}
+
+impl Default for BehaviorBaselines {
+    fn default() -> Self {
+        Self::new(BaselineConfig::default())
+    }
+}
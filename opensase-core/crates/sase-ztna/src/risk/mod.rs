@@ -2,7 +2,12 @@
 //!
 //! Real-time risk evaluation for access decisions.
 
+pub mod baseline;
+pub mod velocity;
+
 use crate::{AccessRequest, RiskSignal, RiskSignalType, RiskSeverity};
+use baseline::{BaselineConfig, BehaviorBaselines};
+use velocity::{GeoVelocityEngine, VelocityConfig};
 
 /// Risk evaluation engine
 pub struct RiskEngine {
@@ -12,6 +17,18 @@ pub struct RiskEngine {
     network_risk: std::collections::HashMap<crate::NetworkType, f64>,
     /// Historical risk data
     user_risk_history: dashmap::DashMap<String, UserRiskProfile>,
+    /// Behavioural baselines for derived signals
+    baselines: BehaviorBaselines,
+    /// Location history for impossible travel
+    velocity: GeoVelocityEngine,
+}
+
+/// Risk score with the signals that contributed to it
+#[derive(Debug, Clone)]
+pub struct RiskAssessment {
+    pub score: f64,
+    /// Signals supplied with the request plus those derived from baselines
+    pub signals: Vec<RiskSignal>,
 }
 
 #[derive(Clone)]
@@ -48,13 +65,52 @@ impl RiskEngine {
             signal_weights,
             network_risk,
             user_risk_history: dashmap::DashMap::new(),
+            baselines: BehaviorBaselines::default(),
+            velocity: GeoVelocityEngine::default(),
         }
     }
     
+    pub fn with_baseline_config(mut self, config: BaselineConfig) -> Self {
+        self.baselines = BehaviorBaselines::new(config);
+        self
+    }
+    
+    /// Per-user behavioural baselines
+    pub fn baselines(&self) -> &BehaviorBaselines {
+        &self.baselines
+    }
+    
+    pub fn with_velocity_config(mut self, config: VelocityConfig) -> Self {
+        self.velocity = GeoVelocityEngine::new(config);
+        self
+    }
+    
+    /// Geo-velocity engine; register VPN egress through it
+    pub fn velocity(&self) -> &GeoVelocityEngine {
+        &self.velocity
+    }
+    
     /// Evaluate risk for access request
     pub async fn evaluate(&self, request: &AccessRequest) -> f64 {
+        self.assess(request).await.score
+    }
+    
+    /// Evaluate risk and report the signals behind the score
+    pub async fn assess(&self, request: &AccessRequest) -> RiskAssessment {
         let mut risk_score = 0.0;
         
+        // Signals from the request, plus any the velocity engine and
+        // baseline derive that the caller did not already supply
+        let mut signals = request.context.signals.clone();
+        let derived = self.velocity.observe(request)
+            .into_iter()
+            .chain(self.baselines.observe(request));
+        for derived in derived {
+            if !signals.iter().any(|s| s.signal_type == derived.signal_type) {
+                signals.push(derived);
+            }
+        }
+        
         // Base risk from network type
         risk_score += self.network_risk
             .get(&request.context.network_type)
@@ -65,7 +121,7 @@ impl RiskEngine {
         risk_score += self.device_risk(&request.device);
         
         // Signal-based risk
-        for signal in &request.context.signals {
+        for signal in &signals {
             risk_score += self.signal_weights
                 .get(&signal.signal_type)
                 .copied()
@@ -90,10 +146,13 @@ impl RiskEngine {
         }
         
         // Update user risk profile
-        self.update_user_profile(&request.identity.user_id, &request.context.signals);
+        self.update_user_profile(&request.identity.user_id, &signals);
         
-        // Cap at 100
-        risk_score.min(100.0)
+        RiskAssessment {
+            // Cap at 100
+            score: risk_score.min(100.0),
+            signals,
+        }
     }
     
     fn device_risk(&self, device: &crate::Device) -> f64 {
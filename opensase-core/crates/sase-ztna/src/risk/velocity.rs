@@ -0,0 +1,279 @@
+//! Geo-Velocity
+//!
+//! Keeps the recent located accesses of each identity and checks every new
+//! access against them: the great-circle distance between two places, less
+//! the geo-IP uncertainty at either end, divided by the time between them
+//! gives the speed the user would have had to travel. Speeds beyond what an
+//! airliner manages raise `ImpossibleTravel`.
+//!
+//! A VPN exit says nothing about where the user is, so accesses from the
+//! registered egress ranges and ASNs of known VPNs (corporate concentrators,
+//! commercial providers) are neither checked nor remembered.
+
+use crate::{AccessRequest, NetworkType, RiskSeverity, RiskSignal, RiskSignalType};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone)]
+pub struct VelocityConfig {
+    /// Fastest plausible travel between two accesses
+    pub max_speed_kmh: f64,
+    /// Distances below this, after accuracy, are treated as noise
+    pub min_travel_km: f64,
+    /// Geo-IP uncertainty assumed when a lookup gives none
+    pub default_accuracy_km: f64,
+    /// How far back accesses are compared
+    pub window: Duration,
+    /// Accesses kept per identity
+    pub max_history: usize,
+}
+
+impl Default for VelocityConfig {
+    fn default() -> Self {
+        Self {
+            max_speed_kmh: 1000.0,
+            min_travel_km: 500.0,
+            default_accuracy_km: 50.0,
+            window: Duration::hours(24),
+            max_history: 16,
+        }
+    }
+}
+
+/// A remembered access with a usable location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocatedAccess {
+    pub at: DateTime<Utc>,
+    pub country: String,
+    pub city: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy_km: f64,
+    pub client_ip: IpAddr,
+    pub device_id: String,
+}
+
+impl LocatedAccess {
+    fn place(&self) -> String {
+        match &self.city {
+            Some(city) => format!("{}, {}", city, self.country),
+            None => self.country.clone(),
+        }
+    }
+}
+
+/// Travel between two accesses faster than the configured limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TravelViolation {
+    pub from: LocatedAccess,
+    pub to: LocatedAccess,
+    /// Distance after subtracting both accuracy radii
+    pub distance_km: f64,
+    pub elapsed_minutes: f64,
+    pub speed_kmh: f64,
+}
+
+/// Egress addresses of VPNs whose exit location is not the user's
+#[derive(Default)]
+pub struct VpnEgressRegistry {
+    networks: parking_lot::RwLock<Vec<(ipnetwork::IpNetwork, String)>>,
+    asns: parking_lot::RwLock<HashMap<u32, String>>,
+}
+
+impl VpnEgressRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an egress range, e.g. a corporate VPN concentrator pool
+    pub fn add_network(&self, network: ipnetwork::IpNetwork, label: &str) {
+        let mut networks = self.networks.write();
+        networks.retain(|(n, _)| *n != network);
+        networks.push((network, label.to_string()));
+    }
+
+    pub fn remove_network(&self, network: &ipnetwork::IpNetwork) -> bool {
+        let mut networks = self.networks.write();
+        let before = networks.len();
+        networks.retain(|(n, _)| n != network);
+        networks.len() != before
+    }
+
+    /// Register an origin AS that only carries VPN egress traffic
+    pub fn add_asn(&self, asn: u32, label: &str) {
+        self.asns.write().insert(asn, label.to_string());
+    }
+
+    pub fn remove_asn(&self, asn: u32) -> bool {
+        self.asns.write().remove(&asn).is_some()
+    }
+
+    /// Label of the VPN an address or AS belongs to
+    pub fn lookup(&self, ip: IpAddr, asn: Option<u32>) -> Option<String> {
+        if let Some((_, label)) = self.networks.read().iter().find(|(n, _)| n.contains(ip)) {
+            return Some(label.clone());
+        }
+        asn.and_then(|asn| self.asns.read().get(&asn).cloned())
+    }
+}
+
+/// Per-identity location history and velocity checks
+pub struct GeoVelocityEngine {
+    config: VelocityConfig,
+    egress: VpnEgressRegistry,
+    history: dashmap::DashMap<String, VecDeque<LocatedAccess>>,
+}
+
+impl GeoVelocityEngine {
+    pub fn new(config: VelocityConfig) -> Self {
+        Self {
+            config,
+            egress: VpnEgressRegistry::new(),
+            history: dashmap::DashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &VelocityConfig {
+        &self.config
+    }
+
+    /// Known VPN egress; register ranges here to exempt them
+    pub fn egress(&self) -> &VpnEgressRegistry {
+        &self.egress
+    }
+
+    /// Check an access against the identity's recent locations, then
+    /// remember it
+    pub fn observe(&self, request: &AccessRequest) -> Option<RiskSignal> {
+        let access = self.locate(request)?;
+        let mut history = self.history.entry(request.identity.user_id.clone()).or_default();
+
+        let cutoff = access.at - self.config.window;
+        history.retain(|a| a.at >= cutoff);
+
+        let violation = history.iter()
+            .filter_map(|previous| self.velocity(previous, &access))
+            .max_by(|a, b| a.speed_kmh.total_cmp(&b.speed_kmh));
+
+        history.push_back(access);
+        while history.len() > self.config.max_history {
+            history.pop_front();
+        }
+        drop(history);
+
+        let violation = violation?;
+        tracing::info!(
+            "Impossible travel for {}: {} -> {} at {:.0} km/h",
+            request.identity.user_id, violation.from.place(), violation.to.place(), violation.speed_kmh
+        );
+        Some(self.signal(&violation))
+    }
+
+    /// Worst violation an access would cause, without remembering it
+    pub fn check(&self, request: &AccessRequest) -> Option<TravelViolation> {
+        let access = self.locate(request)?;
+        let history = self.history.get(&request.identity.user_id)?;
+        let cutoff = access.at - self.config.window;
+
+        history.iter()
+            .filter(|a| a.at >= cutoff)
+            .filter_map(|previous| self.velocity(previous, &access))
+            .max_by(|a, b| a.speed_kmh.total_cmp(&b.speed_kmh))
+    }
+
+    /// Located accesses remembered for an identity, oldest first
+    pub fn history(&self, user_id: &str) -> Vec<LocatedAccess> {
+        self.history.get(user_id)
+            .map(|h| h.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget an identity's locations (e.g. after confirmed travel)
+    pub fn reset(&self, user_id: &str) {
+        self.history.remove(user_id);
+    }
+
+    /// Drop identities whose newest access is outside the window
+    pub fn prune_idle(&self) -> usize {
+        let cutoff = Utc::now() - self.config.window;
+        let before = self.history.len();
+        self.history.retain(|_, h| h.back().is_some_and(|a| a.at >= cutoff));
+        before - self.history.len()
+    }
+
+    /// The access as a location, unless it has none or exits a VPN
+    fn locate(&self, request: &AccessRequest) -> Option<LocatedAccess> {
+        let context = &request.context;
+        if context.network_type == NetworkType::VPN {
+            return None;
+        }
+        let geo = context.geo_location.as_ref()?;
+        if let Some(vpn) = self.egress.lookup(context.client_ip, geo.asn) {
+            tracing::debug!("Skipping velocity for {} via VPN egress {}", request.identity.user_id, vpn);
+            return None;
+        }
+
+        Some(LocatedAccess {
+            at: context.time_of_access,
+            country: geo.country.clone(),
+            city: geo.city.clone(),
+            latitude: geo.latitude,
+            longitude: geo.longitude,
+            accuracy_km: geo.accuracy_km.unwrap_or(self.config.default_accuracy_km),
+            client_ip: context.client_ip,
+            device_id: request.device.id.clone(),
+        })
+    }
+
+    fn velocity(&self, from: &LocatedAccess, to: &LocatedAccess) -> Option<TravelViolation> {
+        let distance = crate::context::haversine_distance(
+            from.latitude, from.longitude,
+            to.latitude, to.longitude,
+        ) - from.accuracy_km - to.accuracy_km;
+        if distance < self.config.min_travel_km {
+            return None;
+        }
+
+        // Clamp to a minute so near-simultaneous accesses don't divide by zero
+        let minutes = (to.at - from.at).num_seconds().abs().max(60) as f64 / 60.0;
+        let speed = distance / (minutes / 60.0);
+        if speed <= self.config.max_speed_kmh {
+            return None;
+        }
+
+        Some(TravelViolation {
+            from: from.clone(),
+            to: to.clone(),
+            distance_km: distance,
+            elapsed_minutes: minutes,
+            speed_kmh: speed,
+        })
+    }
+
+    fn signal(&self, violation: &TravelViolation) -> RiskSignal {
+        let severity = if violation.speed_kmh > self.config.max_speed_kmh * 4.0 {
+            RiskSeverity::Critical
+        } else {
+            RiskSeverity::High
+        };
+
+        RiskSignal {
+            signal_type: RiskSignalType::ImpossibleTravel,
+            severity,
+            description: format!(
+                "{} to {}: {:.0} km in {:.0} minutes ({:.0} km/h)",
+                violation.from.place(), violation.to.place(),
+                violation.distance_km, violation.elapsed_minutes, violation.speed_kmh
+            ),
+            detected_at: Utc::now(),
+        }
+    }
+}
+
+impl Default for GeoVelocityEngine {
+    fn default() -> Self {
+        Self::new(VelocityConfig::default())
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Zero Trust session lifecycle management.
 
-use crate::{Session, SessionStatus, Identity, Device, Resource, TrustLevel};
+use crate::{Session, SessionStatus, Identity, Device, DevicePosture, Resource, TrustLevel};
 use std::collections::HashSet;
 
 /// Session manager
@@ -46,20 +46,45 @@ impl SessionManager {
         }
         
         // Create new session
+        let mut resources = HashSet::new();
+        resources.insert(resource.id.clone());
+        let expires_at = chrono::Utc::now() + chrono::Duration::minutes(self.timeout_mins as i64);
+        self.open(identity, device, resources, expires_at)
+    }
+    
+    /// Open a session for a fresh login. Never reuses an existing session
+    /// id; callers terminate the user's earlier sessions on the device
+    /// first. `not_after` caps the expiry when the IdP limits the session.
+    pub async fn establish(
+        &self,
+        identity: &Identity,
+        device: &Device,
+        not_after: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Session {
+        let mut expires_at = chrono::Utc::now() + chrono::Duration::minutes(self.timeout_mins as i64);
+        if let Some(not_after) = not_after {
+            expires_at = expires_at.min(not_after);
+        }
+        self.open(identity, device, HashSet::new(), expires_at)
+    }
+    
+    fn open(
+        &self,
+        identity: &Identity,
+        device: &Device,
+        active_resources: HashSet<String>,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Session {
         let session = Session {
             id: uuid::Uuid::new_v4().to_string(),
             identity: identity.clone(),
             device: device.clone(),
             created_at: chrono::Utc::now(),
             last_activity: chrono::Utc::now(),
-            expires_at: chrono::Utc::now() + chrono::Duration::minutes(self.timeout_mins as i64),
+            expires_at,
             trust_level: device.trust_level,
             risk_score: 0.0,
-            active_resources: {
-                let mut set = HashSet::new();
-                set.insert(resource.id.clone());
-                set
-            },
+            active_resources,
             status: SessionStatus::Active,
         };
         
@@ -99,6 +124,26 @@ impl SessionManager {
         }
     }
     
+    /// Record a fresh posture report for the session's device
+    pub async fn update_posture(&self, session_id: &str, posture: DevicePosture) {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.device.posture = posture;
+        }
+    }
+    
+    /// Record a re-evaluated risk score and cap the session's trust at
+    /// `ceiling`. Returns the previous trust level when it was lowered.
+    pub async fn downgrade(&self, session_id: &str, ceiling: TrustLevel, risk_score: f64) -> Option<TrustLevel> {
+        let mut session = self.sessions.get_mut(session_id)?;
+        session.risk_score = risk_score;
+        if session.trust_level <= ceiling {
+            return None;
+        }
+        let previous = session.trust_level;
+        session.trust_level = ceiling;
+        Some(previous)
+    }
+    
     /// Terminate session
     pub async fn terminate(&self, session_id: &str) {
         if let Some(mut session) = self.sessions.get_mut(session_id) {
@@ -1,195 +0,0 @@
-//! SSO Integration
-//!
-//! SAML and OIDC SSO support.
-
-use crate::{Identity, IdentityProvider};
-
-/// SSO provider manager
-pub struct SsoManager {
-    /// SAML providers
-    saml_providers: dashmap::DashMap<String, SamlConfig>,
-    /// OIDC providers
-    oidc_providers: dashmap::DashMap<String, OidcConfig>,
-}
-
-#[derive(Debug, Clone)]
-pub struct SamlConfig {
-    pub id: String,
-    pub name: String,
-    pub idp_entity_id: String,
-    pub idp_sso_url: String,
-    pub idp_certificate: String,
-    pub sp_entity_id: String,
-    pub sp_acs_url: String,
-    pub attribute_mapping: AttributeMapping,
-}
-
-#[derive(Debug, Clone)]
-pub struct OidcConfig {
-    pub id: String,
-    pub name: String,
-    pub issuer: String,
-    pub client_id: String,
-    pub client_secret: String,
-    pub authorization_endpoint: String,
-    pub token_endpoint: String,
-    pub userinfo_endpoint: String,
-    pub scopes: Vec<String>,
-    pub attribute_mapping: AttributeMapping,
-}
-
-#[derive(Debug, Clone)]
-pub struct AttributeMapping {
-    pub user_id: String,
-    pub email: String,
-    pub name: String,
-    pub groups: Option<String>,
-    pub roles: Option<String>,
-}
-
-impl Default for AttributeMapping {
-    fn default() -> Self {
-        Self {
-            user_id: "sub".to_string(),
-            email: "email".to_string(),
-            name: "name".to_string(),
-            groups: Some("groups".to_string()),
-            roles: Some("roles".to_string()),
-        }
-    }
-}
-
-impl SsoManager {
-    pub fn new() -> Self {
-        Self {
-            saml_providers: dashmap::DashMap::new(),
-            oidc_providers: dashmap::DashMap::new(),
-        }
-    }
-    
-    /// Add SAML provider
-    pub fn add_saml_provider(&self, config: SamlConfig) {
-        self.saml_providers.insert(config.id.clone(), config);
-    }
-    
-    /// Add OIDC provider
-    pub fn add_oidc_provider(&self, config: OidcConfig) {
-        self.oidc_providers.insert(config.id.clone(), config);
-    }
-    
-    /// Get SAML login URL
-    pub fn get_saml_login_url(&self, provider_id: &str, relay_state: &str) -> Option<String> {
-        self.saml_providers.get(provider_id).map(|config| {
-            format!(
-                "{}?SAMLRequest={}&RelayState={}",
-                config.idp_sso_url,
-                "[encoded_request]",
-                relay_state
-            )
-        })
-    }
-    
-    /// Get OIDC authorization URL
-    pub fn get_oidc_auth_url(&self, provider_id: &str, state: &str, nonce: &str) -> Option<String> {
-        self.oidc_providers.get(provider_id).map(|config| {
-            format!(
-                "{}?client_id={}&response_type=code&scope={}&state={}&nonce={}&redirect_uri={}",
-                config.authorization_endpoint,
-                config.client_id,
-                config.scopes.join("+"),
-                state,
-                nonce,
-                "[callback_url]"
-            )
-        })
-    }
-    
-    /// Process SAML response
-    pub async fn process_saml_response(
-        &self,
-        provider_id: &str,
-        saml_response: &str,
-    ) -> Result<Identity, SsoError> {
-        let config = self.saml_providers.get(provider_id)
-            .ok_or(SsoError::ProviderNotFound)?;
-        
-        // In production: validate SAML response, check signature, parse assertions
-        tracing::info!("Processing SAML response for provider {}", config.name);
-        
-        // Parse identity from SAML assertion
-        let identity = Identity {
-            id: uuid::Uuid::new_v4().to_string(),
-            user_id: "saml_user".to_string(),
-            email: "user@example.com".to_string(),
-            name: "SAML User".to_string(),
-            groups: vec![],
-            roles: vec![],
-            attributes: std::collections::HashMap::new(),
-            mfa_verified: false,
-            verified_at: chrono::Utc::now(),
-            provider: IdentityProvider::Saml { 
-                idp: config.idp_entity_id.clone() 
-            },
-        };
-        
-        Ok(identity)
-    }
-    
-    /// Exchange OIDC code for tokens
-    pub async fn exchange_oidc_code(
-        &self,
-        provider_id: &str,
-        code: &str,
-    ) -> Result<Identity, SsoError> {
-        let config = self.oidc_providers.get(provider_id)
-            .ok_or(SsoError::ProviderNotFound)?;
-        
-        // In production: exchange code for tokens, validate ID token, get userinfo
-        tracing::info!("Exchanging OIDC code for provider {}", config.name);
-        
-        // Parse identity from ID token/userinfo
-        let identity = Identity {
-            id: uuid::Uuid::new_v4().to_string(),
-            user_id: "oidc_user".to_string(),
-            email: "user@example.com".to_string(),
-            name: "OIDC User".to_string(),
-            groups: vec![],
-            roles: vec![],
-            attributes: std::collections::HashMap::new(),
-            mfa_verified: false,
-            verified_at: chrono::Utc::now(),
-            provider: IdentityProvider::Oidc { 
-                issuer: config.issuer.clone() 
-            },
-        };
-        
-        Ok(identity)
-    }
-}
-
-impl Default for SsoManager {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-#[derive(Debug)]
-pub enum SsoError {
-    ProviderNotFound,
-    InvalidResponse,
-    TokenExpired,
-    SignatureInvalid,
-}
-
-impl std::fmt::Display for SsoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::ProviderNotFound => write!(f, "SSO provider not found"),
-            Self::InvalidResponse => write!(f, "Invalid SSO response"),
-            Self::TokenExpired => write!(f, "Token expired"),
-            Self::SignatureInvalid => write!(f, "Signature invalid"),
-        }
-    }
-}
-
-impl std::error::Error for SsoError {}
@@ -0,0 +1,642 @@
+//! SSO Integration
+//!
+//! Per-tenant identity provider federation over OIDC and SAML.
+//!
+//! ```text
+//! begin_login ──► IdP ──► complete_oidc / complete_saml ──► SsoLogin ──► session
+//!  state, nonce,           validate token or assertion,       ZeroTrustGateway::
+//!  PKCE verifier or        map claims and groups              establish_sso_session
+//!  SAML request id
+//! ```
+//!
+//! Each tenant configures its own IdPs; claims are mapped to the identity
+//! through [`AttributeMapping`] and IdP groups to internal groups and
+//! roles through [`GroupMapping`].
+
+mod oidc;
+mod saml;
+mod xml;
+
+pub use saml::{parse_idp_metadata, IdpMetadata};
+
+use crate::audit::AuditLogger;
+use crate::{Identity, IdentityProvider};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Clock skew tolerated on token and assertion timestamps
+const CLOCK_SKEW_SECS: i64 = 120;
+/// Time allowed between redirecting to the IdP and the callback
+const LOGIN_TIMEOUT_MINS: i64 = 10;
+
+/// Claim or attribute values by name
+type Claims = HashMap<String, Vec<String>>;
+
+/// SSO provider manager
+pub struct SsoManager {
+    /// Configured IdPs by id
+    providers: dashmap::DashMap<String, IdpConfig>,
+    /// Logins in flight, by state / RelayState
+    pending: dashmap::DashMap<String, PendingLogin>,
+    /// Consumed SAML assertion IDs, kept until the assertion expires
+    consumed_assertions: dashmap::DashMap<String, DateTime<Utc>>,
+    oidc: oidc::OidcClient,
+    audit: Option<Arc<AuditLogger>>,
+}
+
+/// Identity provider configured for a tenant
+#[derive(Debug, Clone)]
+pub struct IdpConfig {
+    pub id: String,
+    pub tenant_id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub protocol: IdpProtocol,
+    pub attribute_mapping: AttributeMapping,
+    pub group_mapping: GroupMapping,
+    /// Email domains this IdP may assert; empty allows any
+    pub allowed_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdpProtocol {
+    Oidc(OidcConfig),
+    Saml(SamlConfig),
+}
+
+#[derive(Debug, Clone)]
+pub struct SamlConfig {
+    pub idp_entity_id: String,
+    /// HTTP-Redirect SingleSignOnService location
+    pub idp_sso_url: String,
+    /// IdP signing certificates, PEM or base64 DER. List both during a
+    /// certificate rollover.
+    pub idp_certificates: Vec<String>,
+    pub sp_entity_id: String,
+    pub sp_acs_url: String,
+    pub name_id_format: String,
+    /// Require the assertion itself to be signed, not just the response
+    pub want_assertions_signed: bool,
+    /// Accept unsolicited (IdP-initiated) responses
+    pub allow_idp_initiated: bool,
+}
+
+impl SamlConfig {
+    /// Configuration from imported IdP metadata
+    pub fn from_metadata(metadata: IdpMetadata, sp_entity_id: &str, sp_acs_url: &str) -> Self {
+        Self {
+            idp_entity_id: metadata.entity_id,
+            idp_sso_url: metadata.sso_url,
+            idp_certificates: metadata.certificates,
+            sp_entity_id: sp_entity_id.to_string(),
+            sp_acs_url: sp_acs_url.to_string(),
+            name_id_format: "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress".to_string(),
+            want_assertions_signed: true,
+            allow_idp_initiated: false,
+        }
+    }
+
+    /// Signing certificates as DER
+    pub(crate) fn certificates(&self) -> Result<Vec<Vec<u8>>, SsoError> {
+        let certificates: Vec<Vec<u8>> = self.idp_certificates.iter()
+            .filter_map(|c| saml::certificate_der(c))
+            .collect();
+        if certificates.is_empty() {
+            return Err(SsoError::Configuration("no usable IdP signing certificate".to_string()));
+        }
+        Ok(certificates)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer identifier; discovery is fetched from under it
+    pub issuer: String,
+    pub client_id: String,
+    /// None for public clients, which rely on PKCE alone
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// Fetch UserInfo for claims the ID token leaves out
+    pub fetch_userinfo: bool,
+    /// `prompt` parameter, e.g. `login` or `select_account`
+    pub prompt: Option<String>,
+}
+
+impl OidcConfig {
+    pub fn new(issuer: &str, client_id: &str, redirect_uri: &str) -> Self {
+        Self {
+            issuer: issuer.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: None,
+            redirect_uri: redirect_uri.to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+            fetch_userinfo: true,
+            prompt: None,
+        }
+    }
+}
+
+/// Claim names to read identity fields from. OIDC claims nested in
+/// objects are addressed with dots, e.g. `realm_access.roles`.
+#[derive(Debug, Clone)]
+pub struct AttributeMapping {
+    pub user_id: String,
+    pub email: String,
+    pub name: String,
+    pub groups: Option<String>,
+    pub roles: Option<String>,
+    /// `amr`/`acr` values or SAML AuthnContextClassRefs that mean the IdP
+    /// performed MFA
+    pub mfa_indicators: Vec<String>,
+}
+
+impl Default for AttributeMapping {
+    fn default() -> Self {
+        Self {
+            user_id: "sub".to_string(),
+            email: "email".to_string(),
+            name: "name".to_string(),
+            groups: Some("groups".to_string()),
+            roles: Some("roles".to_string()),
+            mfa_indicators: vec![
+                "mfa".to_string(),
+                "http://schemas.microsoft.com/claims/multipleauthn".to_string(),
+                "urn:oasis:names:tc:SAML:2.0:ac:classes:MobileTwoFactorContract".to_string(),
+                "urn:oasis:names:tc:SAML:2.0:ac:classes:TimeSyncToken".to_string(),
+            ],
+        }
+    }
+}
+
+impl AttributeMapping {
+    /// Defaults for SAML: the NameID as user id and common attribute names
+    pub fn saml() -> Self {
+        Self {
+            user_id: "NameID".to_string(),
+            email: "email".to_string(),
+            name: "displayName".to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// IdP group and role values to internal groups and roles
+#[derive(Debug, Clone, Default)]
+pub struct GroupMapping {
+    pub rules: Vec<GroupRule>,
+    /// Keep IdP groups no rule matched, under their own names
+    pub pass_through: bool,
+    /// Groups every user of this IdP gets
+    pub default_groups: Vec<String>,
+    /// Refuse users who end up with no groups or roles
+    pub require_group: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupRule {
+    /// IdP group name or id, matched case-insensitively
+    pub idp_group: String,
+    pub groups: Vec<String>,
+    pub roles: Vec<String>,
+}
+
+impl GroupMapping {
+    /// Internal (groups, roles) for the values an IdP asserted
+    pub fn apply(&self, idp_groups: &[String]) -> (Vec<String>, Vec<String>) {
+        let mut groups = self.default_groups.clone();
+        let mut roles = Vec::new();
+
+        for idp_group in idp_groups {
+            let mut matched = false;
+            for rule in self.rules.iter().filter(|r| r.idp_group.eq_ignore_ascii_case(idp_group)) {
+                matched = true;
+                groups.extend(rule.groups.iter().cloned());
+                roles.extend(rule.roles.iter().cloned());
+            }
+            if !matched && self.pass_through {
+                groups.push(idp_group.clone());
+            }
+        }
+
+        groups.sort();
+        groups.dedup();
+        roles.sort();
+        roles.dedup();
+        (groups, roles)
+    }
+}
+
+/// Where to send the browser to start a login
+#[derive(Debug, Clone)]
+pub struct LoginRedirect {
+    pub url: String,
+    /// OIDC `state` / SAML RelayState identifying the login
+    pub state: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Completed federated login
+#[derive(Debug, Clone)]
+pub struct SsoLogin {
+    pub tenant_id: String,
+    pub provider_id: String,
+    pub identity: Identity,
+    /// Where the user was headed when the login started
+    pub return_to: Option<String>,
+    /// Latest time a session from this login may last, when the IdP
+    /// limits it
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+struct PendingLogin {
+    provider_id: String,
+    return_to: Option<String>,
+    created_at: DateTime<Utc>,
+    flow: PendingFlow,
+}
+
+enum PendingFlow {
+    Oidc { nonce: String, verifier: String },
+    Saml { request_id: String },
+}
+
+impl SsoManager {
+    pub fn new() -> Self {
+        Self {
+            providers: dashmap::DashMap::new(),
+            pending: dashmap::DashMap::new(),
+            consumed_assertions: dashmap::DashMap::new(),
+            oidc: oidc::OidcClient::new(),
+            audit: None,
+        }
+    }
+
+    /// Record federated logins in the audit trail
+    pub fn with_audit(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Add or replace an IdP
+    pub fn add_provider(&self, config: IdpConfig) -> Result<(), SsoError> {
+        if let IdpProtocol::Saml(saml) = &config.protocol {
+            saml.certificates()?;
+        }
+        self.providers.insert(config.id.clone(), config);
+        Ok(())
+    }
+
+    pub fn remove_provider(&self, provider_id: &str) -> Option<IdpConfig> {
+        self.providers.remove(provider_id).map(|(_, config)| config)
+    }
+
+    pub fn provider(&self, provider_id: &str) -> Option<IdpConfig> {
+        self.providers.get(provider_id).map(|p| p.clone())
+    }
+
+    /// Enabled IdPs of a tenant, for its login page
+    pub fn providers_for(&self, tenant_id: &str) -> Vec<IdpConfig> {
+        self.providers.iter()
+            .filter(|p| p.tenant_id == tenant_id && p.enabled)
+            .map(|p| p.clone())
+            .collect()
+    }
+
+    /// SP metadata for a SAML provider, for the tenant's IdP admin
+    pub fn sp_metadata(&self, provider_id: &str) -> Result<String, SsoError> {
+        let config = self.provider(provider_id).ok_or(SsoError::ProviderNotFound)?;
+        match &config.protocol {
+            IdpProtocol::Saml(saml) => Ok(saml::sp_metadata(saml)),
+            IdpProtocol::Oidc(_) => Err(SsoError::Configuration("not a SAML provider".to_string())),
+        }
+    }
+
+    /// Start a login: remember the flow and return the IdP redirect
+    pub async fn begin_login(&self, provider_id: &str, return_to: Option<&str>) -> Result<LoginRedirect, SsoError> {
+        let config = self.enabled_provider(provider_id)?;
+        self.purge_expired();
+
+        let state = oidc::random_token();
+        let (url, flow) = match &config.protocol {
+            IdpProtocol::Oidc(oidc_config) => {
+                let metadata = self.oidc.discover(oidc_config).await?;
+                let nonce = oidc::random_token();
+                let verifier = oidc::random_token();
+                let url = self.oidc.authorization_url(&metadata, oidc_config, &state, &nonce, &verifier)?;
+                (url, PendingFlow::Oidc { nonce, verifier })
+            }
+            IdpProtocol::Saml(saml_config) => {
+                let request_id = saml::request_id();
+                let url = saml::authn_request_url(saml_config, &request_id, &state)?;
+                (url, PendingFlow::Saml { request_id })
+            }
+        };
+
+        let created_at = Utc::now();
+        self.pending.insert(state.clone(), PendingLogin {
+            provider_id: provider_id.to_string(),
+            return_to: return_to.map(String::from),
+            created_at,
+            flow,
+        });
+
+        Ok(LoginRedirect {
+            url,
+            state,
+            expires_at: created_at + Duration::minutes(LOGIN_TIMEOUT_MINS),
+        })
+    }
+
+    /// Finish an OIDC login from the redirect URI's `state` and `code`
+    pub async fn complete_oidc(&self, state: &str, code: &str) -> Result<SsoLogin, SsoError> {
+        let pending = self.take_pending(state)?;
+        let config = self.enabled_provider(&pending.provider_id)?;
+        let result = self.oidc_login(&config, &pending, code).await;
+        self.audit_login(&config, &result).await;
+        result
+    }
+
+    /// Finish a SAML login from the ACS form post. Without a RelayState
+    /// from [`SsoManager::begin_login`] the response is treated as
+    /// IdP-initiated.
+    pub async fn complete_saml(&self, saml_response: &str, relay_state: Option<&str>) -> Result<SsoLogin, SsoError> {
+        let response = saml::decode_response(saml_response)?;
+
+        let pending = match relay_state.map(|state| self.take_pending(state)) {
+            Some(Ok(pending)) => Some(pending),
+            // IdP-initiated logins may carry a RelayState of their own
+            Some(Err(_)) | None => None,
+        };
+        let (config, request_id, return_to) = match &pending {
+            Some(pending) => match &pending.flow {
+                PendingFlow::Saml { request_id } => (
+                    self.enabled_provider(&pending.provider_id)?,
+                    Some(request_id.as_str()),
+                    pending.return_to.clone(),
+                ),
+                PendingFlow::Oidc { .. } => return Err(SsoError::UnknownState),
+            },
+            None => {
+                let issuer = saml::response_issuer(&response).ok_or(SsoError::UnknownState)?;
+                let config = self.providers.iter()
+                    .find(|p| p.enabled && matches!(&p.protocol, IdpProtocol::Saml(s) if s.idp_entity_id == issuer && s.allow_idp_initiated))
+                    .map(|p| p.clone())
+                    .ok_or(SsoError::UnknownState)?;
+                (config, None, None)
+            }
+        };
+
+        let result = self.saml_login(&config, &response, request_id, return_to);
+        self.audit_login(&config, &result).await;
+        result
+    }
+
+    /// Drop abandoned logins and expired assertion IDs
+    pub fn purge_expired(&self) -> usize {
+        let now = Utc::now();
+        let cutoff = now - Duration::minutes(LOGIN_TIMEOUT_MINS);
+        let before = self.pending.len() + self.consumed_assertions.len();
+        self.pending.retain(|_, login| login.created_at > cutoff);
+        self.consumed_assertions.retain(|_, expires| *expires > now);
+        before - (self.pending.len() + self.consumed_assertions.len())
+    }
+
+    fn enabled_provider(&self, provider_id: &str) -> Result<IdpConfig, SsoError> {
+        let config = self.provider(provider_id).ok_or(SsoError::ProviderNotFound)?;
+        if !config.enabled {
+            return Err(SsoError::ProviderDisabled);
+        }
+        Ok(config)
+    }
+
+    /// Single use: a state is consumed whether or not the login succeeds
+    fn take_pending(&self, state: &str) -> Result<PendingLogin, SsoError> {
+        let (_, pending) = self.pending.remove(state).ok_or(SsoError::UnknownState)?;
+        if Utc::now() - pending.created_at > Duration::minutes(LOGIN_TIMEOUT_MINS) {
+            return Err(SsoError::UnknownState);
+        }
+        Ok(pending)
+    }
+
+    async fn oidc_login(&self, config: &IdpConfig, pending: &PendingLogin, code: &str) -> Result<SsoLogin, SsoError> {
+        let (IdpProtocol::Oidc(oidc_config), PendingFlow::Oidc { nonce, verifier }) = (&config.protocol, &pending.flow) else {
+            return Err(SsoError::UnknownState);
+        };
+
+        let metadata = self.oidc.discover(oidc_config).await?;
+        let tokens = self.oidc.exchange(&metadata, oidc_config, code, verifier).await?;
+        let mut claims = self.oidc.validate_id_token(&metadata, oidc_config, &tokens.id_token, nonce).await?;
+        let subject = claims.get("sub").and_then(Value::as_str).unwrap_or_default().to_string();
+
+        if oidc_config.fetch_userinfo {
+            if let Some(access_token) = &tokens.access_token {
+                // ID token claims win over UserInfo
+                for (name, value) in self.oidc.userinfo(&metadata, access_token, &subject).await? {
+                    claims.entry(name).or_insert(value);
+                }
+            }
+        }
+
+        let mut flat = Claims::new();
+        for (name, value) in &claims {
+            flatten(name, value, &mut flat);
+        }
+        let mfa = ["amr", "acr"].iter()
+            .filter_map(|name| flat.get(*name))
+            .flatten()
+            .any(|value| config.attribute_mapping.mfa_indicators.contains(value));
+        let identity = map_identity(
+            config,
+            &subject,
+            &flat,
+            mfa,
+            IdentityProvider::Oidc { issuer: oidc_config.issuer.clone() },
+        )?;
+
+        Ok(SsoLogin {
+            tenant_id: config.tenant_id.clone(),
+            provider_id: config.id.clone(),
+            identity,
+            return_to: pending.return_to.clone(),
+            // ID token lifetime says nothing about the IdP session
+            not_after: None,
+        })
+    }
+
+    fn saml_login(
+        &self,
+        config: &IdpConfig,
+        response: &xml::Element,
+        request_id: Option<&str>,
+        return_to: Option<String>,
+    ) -> Result<SsoLogin, SsoError> {
+        let IdpProtocol::Saml(saml_config) = &config.protocol else {
+            return Err(SsoError::UnknownState);
+        };
+
+        let assertion = saml::validate_response(saml_config, response, request_id)?;
+        let key = format!("{}|{}", saml_config.idp_entity_id, assertion.id);
+        if self.consumed_assertions.insert(key, assertion.not_on_or_after).is_some() {
+            return Err(SsoError::Replay);
+        }
+
+        let mut claims = assertion.attributes;
+        claims.insert("NameID".to_string(), vec![assertion.name_id.clone()]);
+        let mfa = assertion.authn_context
+            .is_some_and(|context| config.attribute_mapping.mfa_indicators.contains(&context));
+        let identity = map_identity(
+            config,
+            &assertion.name_id,
+            &claims,
+            mfa,
+            IdentityProvider::Saml { idp: saml_config.idp_entity_id.clone() },
+        )?;
+
+        Ok(SsoLogin {
+            tenant_id: config.tenant_id.clone(),
+            provider_id: config.id.clone(),
+            identity,
+            return_to,
+            not_after: assertion.session_not_on_or_after,
+        })
+    }
+
+    async fn audit_login(&self, config: &IdpConfig, result: &Result<SsoLogin, SsoError>) {
+        let Some(audit) = &self.audit else { return };
+        let (user_id, error) = match result {
+            Ok(login) => (Some(login.identity.user_id.as_str()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        audit.log_federated_login(&config.tenant_id, &config.id, user_id, error.as_deref()).await;
+    }
+}
+
+impl Default for SsoManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// JSON claims as string lists; nested objects under dotted names
+fn flatten(name: &str, value: &Value, out: &mut Claims) {
+    match value {
+        Value::Null => {}
+        Value::String(s) => out.entry(name.to_string()).or_default().push(s.clone()),
+        Value::Array(items) => {
+            for item in items {
+                flatten(name, item, out);
+            }
+        }
+        Value::Object(fields) => {
+            for (field, nested) in fields {
+                flatten(&format!("{}.{}", name, field), nested, out);
+            }
+        }
+        other => out.entry(name.to_string()).or_default().push(other.to_string()),
+    }
+}
+
+/// Build the identity from mapped claims and apply the group mapping
+fn map_identity(
+    config: &IdpConfig,
+    subject: &str,
+    claims: &Claims,
+    mfa_verified: bool,
+    provider: IdentityProvider,
+) -> Result<Identity, SsoError> {
+    let mapping = &config.attribute_mapping;
+    let first = |name: &str| claims.get(name).and_then(|values| values.first()).cloned();
+    let all = |name: &Option<String>| {
+        name.as_ref().and_then(|n| claims.get(n)).cloned().unwrap_or_default()
+    };
+
+    if subject.is_empty() {
+        return Err(SsoError::ClaimMissing("subject".to_string()));
+    }
+    let user_id = first(&mapping.user_id).ok_or_else(|| SsoError::ClaimMissing(mapping.user_id.clone()))?;
+    let email = first(&mapping.email).unwrap_or_default();
+    if !config.allowed_domains.is_empty() {
+        let domain = email.rsplit_once('@').map(|(_, d)| d.to_ascii_lowercase()).unwrap_or_default();
+        if !config.allowed_domains.iter().any(|d| d.eq_ignore_ascii_case(&domain)) {
+            return Err(SsoError::DomainNotAllowed(domain));
+        }
+    }
+    let name = first(&mapping.name).unwrap_or_else(|| email.clone());
+
+    let mut idp_groups = all(&mapping.groups);
+    idp_groups.extend(all(&mapping.roles));
+    let (groups, roles) = config.group_mapping.apply(&idp_groups);
+    if config.group_mapping.require_group && groups.is_empty() && roles.is_empty() {
+        return Err(SsoError::NotAuthorized(format!("{} has no mapped groups", user_id)));
+    }
+
+    let mut attributes: HashMap<String, String> = claims.iter()
+        .filter(|(_, values)| values.len() == 1)
+        .map(|(name, values)| (name.clone(), values[0].clone()))
+        .collect();
+    attributes.insert("tenant_id".to_string(), config.tenant_id.clone());
+    attributes.insert("idp_id".to_string(), config.id.clone());
+
+    Ok(Identity {
+        // Subjects are only unique per IdP
+        id: format!("{}:{}", config.id, subject),
+        user_id,
+        email,
+        name,
+        groups,
+        roles,
+        attributes,
+        mfa_verified,
+        verified_at: Utc::now(),
+        provider,
+    })
+}
+
+#[derive(Debug)]
+pub enum SsoError {
+    ProviderNotFound,
+    ProviderDisabled,
+    /// State or RelayState unknown, expired or already used
+    UnknownState,
+    InvalidResponse(String),
+    TokenExpired,
+    SignatureInvalid,
+    UnsupportedAlgorithm(String),
+    /// Assertion already consumed
+    Replay,
+    /// The IdP reported an error
+    Idp(String),
+    Discovery(String),
+    Http(String),
+    Configuration(String),
+    ClaimMissing(String),
+    DomainNotAllowed(String),
+    NotAuthorized(String),
+}
+
+impl std::fmt::Display for SsoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ProviderNotFound => write!(f, "SSO provider not found"),
+            Self::ProviderDisabled => write!(f, "SSO provider disabled"),
+            Self::UnknownState => write!(f, "Unknown or expired login"),
+            Self::InvalidResponse(e) => write!(f, "Invalid SSO response: {}", e),
+            Self::TokenExpired => write!(f, "Token expired"),
+            Self::SignatureInvalid => write!(f, "Signature invalid"),
+            Self::UnsupportedAlgorithm(a) => write!(f, "Unsupported algorithm: {}", a),
+            Self::Replay => write!(f, "Assertion already used"),
+            Self::Idp(e) => write!(f, "Identity provider error: {}", e),
+            Self::Discovery(e) => write!(f, "Provider discovery failed: {}", e),
+            Self::Http(e) => write!(f, "Identity provider unreachable: {}", e),
+            Self::Configuration(e) => write!(f, "SSO configuration error: {}", e),
+            Self::ClaimMissing(c) => write!(f, "Required claim missing: {}", c),
+            Self::DomainNotAllowed(d) => write!(f, "Email domain not allowed: {}", d),
+            Self::NotAuthorized(e) => write!(f, "Not authorized: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SsoError {}
@@ -0,0 +1,278 @@
+//! OpenID Connect relying party
+//!
+//! Authorization code flow with PKCE (S256) against any provider that
+//! publishes discovery metadata. ID tokens are verified against the
+//! provider's JWKS, refetched once when a token names an unknown key so
+//! rotations are picked up without a restart. Symmetric (HS*) ID tokens
+//! are refused.
+
+use super::{OidcConfig, SsoError, CLOCK_SKEW_SECS};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64url;
+use base64::Engine;
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long discovery documents and key sets are cached
+const METADATA_TTL: Duration = Duration::from_secs(3600);
+
+/// Provider discovery document (the fields used)
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+    #[serde(default)]
+    pub code_challenge_methods_supported: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenResponse {
+    pub id_token: String,
+    #[serde(default)]
+    pub access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+pub(crate) struct OidcClient {
+    http: reqwest::Client,
+    /// Discovery documents by issuer
+    metadata: dashmap::DashMap<String, (Instant, Arc<ProviderMetadata>)>,
+    /// Key sets by JWKS URI
+    keys: dashmap::DashMap<String, (Instant, Arc<JwkSet>)>,
+}
+
+/// Random URL-safe value for state, nonce and PKCE verifiers
+pub(crate) fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    b64url.encode(bytes)
+}
+
+/// S256 code challenge for a PKCE verifier
+fn code_challenge(verifier: &str) -> String {
+    b64url.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+impl OidcClient {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            metadata: dashmap::DashMap::new(),
+            keys: dashmap::DashMap::new(),
+        }
+    }
+
+    /// Discovery document for the configured issuer
+    pub async fn discover(&self, config: &OidcConfig) -> Result<Arc<ProviderMetadata>, SsoError> {
+        if let Some(entry) = self.metadata.get(&config.issuer) {
+            if entry.0.elapsed() < METADATA_TTL {
+                return Ok(entry.1.clone());
+            }
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", config.issuer.trim_end_matches('/'));
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+        // Mix-up defence: the document must be for the issuer we asked
+        if metadata.issuer != config.issuer {
+            return Err(SsoError::Discovery(format!("{} claims issuer {}", url, metadata.issuer)));
+        }
+        let metadata = Arc::new(metadata);
+        self.metadata.insert(config.issuer.clone(), (Instant::now(), metadata.clone()));
+        Ok(metadata)
+    }
+
+    /// Authorization endpoint URL for a code flow with PKCE
+    pub fn authorization_url(
+        &self,
+        metadata: &ProviderMetadata,
+        config: &OidcConfig,
+        state: &str,
+        nonce: &str,
+        verifier: &str,
+    ) -> Result<String, SsoError> {
+        if let Some(methods) = &metadata.code_challenge_methods_supported {
+            if !methods.iter().any(|m| m == "S256") {
+                return Err(SsoError::Discovery("provider does not support PKCE S256".to_string()));
+            }
+        }
+
+        let challenge = code_challenge(verifier);
+        let scope = config.scopes.join(" ");
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("scope", scope.as_str()),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ];
+        if let Some(prompt) = &config.prompt {
+            params.push(("prompt", prompt.as_str()));
+        }
+
+        reqwest::Url::parse_with_params(&metadata.authorization_endpoint, &params)
+            .map(String::from)
+            .map_err(|e| SsoError::Discovery(format!("invalid authorization endpoint: {}", e)))
+    }
+
+    /// Redeem an authorization code
+    pub async fn exchange(
+        &self,
+        metadata: &ProviderMetadata,
+        config: &OidcConfig,
+        code: &str,
+        verifier: &str,
+    ) -> Result<TokenResponse, SsoError> {
+        let form = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", verifier),
+        ];
+        let mut request = self.http.post(&metadata.token_endpoint).form(&form);
+        if let Some(secret) = &config.client_secret {
+            request = request.basic_auth(&config.client_id, Some(secret));
+        }
+
+        let response = request.send().await.map_err(|e| SsoError::Http(e.to_string()))?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| SsoError::Http(e.to_string()))?;
+        if !status.is_success() {
+            return Err(match serde_json::from_slice::<TokenError>(&body) {
+                Ok(error) => SsoError::Idp(match error.error_description {
+                    Some(description) => format!("{}: {}", error.error, description),
+                    None => error.error,
+                }),
+                Err(_) => SsoError::Http(format!("token endpoint returned {}", status)),
+            });
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| SsoError::InvalidResponse(format!("token response: {}", e)))
+    }
+
+    /// Verify an ID token's signature and standard claims and return its
+    /// claims
+    pub async fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        config: &OidcConfig,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<Map<String, Value>, SsoError> {
+        let header = jsonwebtoken::decode_header(id_token)
+            .map_err(|e| SsoError::InvalidResponse(format!("ID token: {}", e)))?;
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(SsoError::UnsupportedAlgorithm(format!("{:?}", header.alg)));
+        }
+
+        let keys = self.key_set(&metadata.jwks_uri, false).await?;
+        let jwk = match &header.kid {
+            Some(kid) => match keys.find(kid).cloned() {
+                Some(jwk) => jwk,
+                None => self.key_set(&metadata.jwks_uri, true).await?
+                    .find(kid)
+                    .cloned()
+                    .ok_or(SsoError::SignatureInvalid)?,
+            },
+            // Without a kid the set must be unambiguous
+            None if keys.keys.len() == 1 => keys.keys[0].clone(),
+            None => return Err(SsoError::InvalidResponse("ID token has no kid".to_string())),
+        };
+        let key = DecodingKey::from_jwk(&jwk).map_err(|_| SsoError::SignatureInvalid)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&config.issuer]);
+        validation.set_audience(&[&config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub"]);
+        validation.leeway = CLOCK_SKEW_SECS as u64;
+
+        let claims = jsonwebtoken::decode::<Map<String, Value>>(id_token, &key, &validation)
+            .map_err(|e| match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => SsoError::TokenExpired,
+                jsonwebtoken::errors::ErrorKind::InvalidSignature => SsoError::SignatureInvalid,
+                _ => SsoError::InvalidResponse(format!("ID token: {}", e)),
+            })?
+            .claims;
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+            return Err(SsoError::InvalidResponse("ID token nonce mismatch".to_string()));
+        }
+        // With several audiences the token must name us as the authorized party
+        let multiple_audiences = claims.get("aud").and_then(Value::as_array).is_some_and(|a| a.len() > 1);
+        let azp = claims.get("azp").and_then(Value::as_str);
+        if (multiple_audiences || azp.is_some()) && azp != Some(config.client_id.as_str()) {
+            return Err(SsoError::InvalidResponse("ID token azp is not this client".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    /// UserInfo claims; `sub` must match the ID token's
+    pub async fn userinfo(
+        &self,
+        metadata: &ProviderMetadata,
+        access_token: &str,
+        subject: &str,
+    ) -> Result<Map<String, Value>, SsoError> {
+        let Some(endpoint) = &metadata.userinfo_endpoint else {
+            return Ok(Map::new());
+        };
+        let response = self.http.get(endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| SsoError::Http(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(SsoError::Http(format!("userinfo returned {}", response.status())));
+        }
+        let claims: Map<String, Value> = response.json().await
+            .map_err(|e| SsoError::InvalidResponse(format!("userinfo: {}", e)))?;
+        if claims.get("sub").and_then(Value::as_str) != Some(subject) {
+            return Err(SsoError::InvalidResponse("userinfo sub does not match the ID token".to_string()));
+        }
+        Ok(claims)
+    }
+
+    async fn key_set(&self, jwks_uri: &str, refresh: bool) -> Result<Arc<JwkSet>, SsoError> {
+        if !refresh {
+            if let Some(entry) = self.keys.get(jwks_uri) {
+                if entry.0.elapsed() < METADATA_TTL {
+                    return Ok(entry.1.clone());
+                }
+            }
+        }
+        let keys: Arc<JwkSet> = Arc::new(self.get_json(jwks_uri).await?);
+        self.keys.insert(jwks_uri.to_string(), (Instant::now(), keys.clone()));
+        Ok(keys)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, SsoError> {
+        let response = self.http.get(url).send().await
+            .map_err(|e| SsoError::Discovery(format!("{}: {}", url, e)))?;
+        if !response.status().is_success() {
+            return Err(SsoError::Discovery(format!("{} returned {}", url, response.status())));
+        }
+        response.json().await
+            .map_err(|e| SsoError::Discovery(format!("{}: {}", url, e)))
+    }
+}
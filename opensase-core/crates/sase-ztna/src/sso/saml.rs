@@ -0,0 +1,780 @@
+//! SAML 2.0 service provider
+//!
+//! - SP metadata for the IdP administrator to import, and IdP metadata
+//!   import in the other direction
+//! - AuthnRequest over the HTTP-Redirect binding
+//! - Response validation for the HTTP-POST binding: the XML signature on
+//!   the assertion or the enclosing response, checked only against the
+//!   configured IdP certificates, then issuer, audience, recipient,
+//!   validity window and InResponseTo
+//!
+//! Encrypted assertions are not supported. The SP metadata publishes no
+//! encryption key, so IdPs send assertions signed but in the clear.
+
+use super::xml::{self, Element};
+use super::{SamlConfig, SsoError, CLOCK_SKEW_SECS};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const METADATA_NS: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const REDIRECT_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+const POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+
+/// Validated assertion contents
+#[derive(Debug, Clone)]
+pub(crate) struct Assertion {
+    pub id: String,
+    pub name_id: String,
+    /// End of the assertion's validity, for the replay cache
+    pub not_on_or_after: DateTime<Utc>,
+    /// End of the IdP session, if the IdP limits it
+    pub session_not_on_or_after: Option<DateTime<Utc>>,
+    pub authn_context: Option<String>,
+    /// Attribute values by Name, and by FriendlyName where given
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+/// What an IdP's metadata says about it
+#[derive(Debug, Clone)]
+pub struct IdpMetadata {
+    pub entity_id: String,
+    /// HTTP-Redirect SingleSignOnService location
+    pub sso_url: String,
+    /// Signing certificates, base64 DER
+    pub certificates: Vec<String>,
+}
+
+// =============================================================================
+// Outbound: AuthnRequest and metadata
+// =============================================================================
+
+/// Fresh request ID; xs:ID values must not start with a digit
+pub(crate) fn request_id() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("_{}", hex_string(&bytes))
+}
+
+/// IdP URL carrying a deflated, base64 AuthnRequest (HTTP-Redirect binding)
+pub(crate) fn authn_request_url(config: &SamlConfig, request_id: &str, relay_state: &str) -> Result<String, SsoError> {
+    let request = format!(
+        "<samlp:AuthnRequest xmlns:samlp=\"{}\" xmlns:saml=\"{}\" ID=\"{}\" Version=\"2.0\" \
+         IssueInstant=\"{}\" Destination=\"{}\" AssertionConsumerServiceURL=\"{}\" ProtocolBinding=\"{}\">\
+         <saml:Issuer>{}</saml:Issuer>\
+         <samlp:NameIDPolicy Format=\"{}\" AllowCreate=\"true\"/>\
+         </samlp:AuthnRequest>",
+        PROTOCOL_NS,
+        ASSERTION_NS,
+        request_id,
+        Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        xml::escape(&config.idp_sso_url),
+        xml::escape(&config.sp_acs_url),
+        POST_BINDING,
+        xml::escape(&config.sp_entity_id),
+        xml::escape(&config.name_id_format),
+    );
+    let encoded = base64::engine::general_purpose::STANDARD.encode(deflate_stored(request.as_bytes()));
+
+    reqwest::Url::parse_with_params(
+        &config.idp_sso_url,
+        &[("SAMLRequest", encoded.as_str()), ("RelayState", relay_state)],
+    )
+    .map(String::from)
+    .map_err(|e| SsoError::Configuration(format!("invalid IdP SSO URL: {}", e)))
+}
+
+/// SP metadata document for the IdP to import
+pub(crate) fn sp_metadata(config: &SamlConfig) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <md:EntityDescriptor xmlns:md=\"{}\" entityID=\"{}\">\
+         <md:SPSSODescriptor AuthnRequestsSigned=\"false\" WantAssertionsSigned=\"{}\" \
+         protocolSupportEnumeration=\"{}\">\
+         <md:NameIDFormat>{}</md:NameIDFormat>\
+         <md:AssertionConsumerService Binding=\"{}\" Location=\"{}\" index=\"0\" isDefault=\"true\"/>\
+         </md:SPSSODescriptor>\
+         </md:EntityDescriptor>\n",
+        METADATA_NS,
+        xml::escape(&config.sp_entity_id),
+        config.want_assertions_signed,
+        PROTOCOL_NS,
+        xml::escape(&config.name_id_format),
+        POST_BINDING,
+        xml::escape(&config.sp_acs_url),
+    )
+}
+
+/// Entity id, SSO location and signing certificates from IdP metadata
+pub fn parse_idp_metadata(document: &str) -> Result<IdpMetadata, SsoError> {
+    let root = xml::parse(document).map_err(|e| SsoError::Configuration(e.to_string()))?;
+    let entity = if root.is(METADATA_NS, "EntitiesDescriptor") {
+        root.descendants().into_iter()
+            .find(|e| e.is(METADATA_NS, "EntityDescriptor") && e.child(METADATA_NS, "IDPSSODescriptor").is_some())
+            .ok_or_else(|| SsoError::Configuration("no IdP entity in metadata".to_string()))?
+    } else {
+        &root
+    };
+    let descriptor = entity.child(METADATA_NS, "IDPSSODescriptor")
+        .ok_or_else(|| SsoError::Configuration("metadata has no IDPSSODescriptor".to_string()))?;
+
+    let entity_id = entity.attribute("entityID")
+        .ok_or_else(|| SsoError::Configuration("metadata has no entityID".to_string()))?;
+    let sso_url = descriptor.children_named(METADATA_NS, "SingleSignOnService")
+        .find(|s| s.attribute("Binding") == Some(REDIRECT_BINDING))
+        .and_then(|s| s.attribute("Location"))
+        .ok_or_else(|| SsoError::Configuration("IdP has no HTTP-Redirect SingleSignOnService".to_string()))?;
+
+    let certificates: Vec<String> = descriptor.children_named(METADATA_NS, "KeyDescriptor")
+        .filter(|k| k.attribute("use").is_none_or(|u| u == "signing"))
+        .flat_map(|k| k.descendants().into_iter().filter(|e| e.is(DSIG_NS, "X509Certificate")))
+        .map(|e| e.text().split_whitespace().collect())
+        .collect();
+    if certificates.is_empty() {
+        return Err(SsoError::Configuration("IdP metadata has no signing certificate".to_string()));
+    }
+
+    Ok(IdpMetadata {
+        entity_id: entity_id.to_string(),
+        sso_url: sso_url.to_string(),
+        certificates,
+    })
+}
+
+// =============================================================================
+// Inbound: Response validation
+// =============================================================================
+
+/// Parse the base64 SAMLResponse form field
+pub(crate) fn decode_response(encoded: &str) -> Result<Element, SsoError> {
+    let compact: String = encoded.split_whitespace().collect();
+    let bytes = base64::engine::general_purpose::STANDARD.decode(compact)
+        .map_err(|_| SsoError::InvalidResponse("SAMLResponse is not base64".to_string()))?;
+    let text = String::from_utf8(bytes)
+        .map_err(|_| SsoError::InvalidResponse("SAMLResponse is not UTF-8".to_string()))?;
+    let root = xml::parse(&text).map_err(|e| SsoError::InvalidResponse(e.to_string()))?;
+    if !root.is(PROTOCOL_NS, "Response") {
+        return Err(SsoError::InvalidResponse(format!("expected samlp:Response, got {}", root.name)));
+    }
+    Ok(root)
+}
+
+/// Issuer of a response, to find the provider for IdP-initiated logins
+pub(crate) fn response_issuer(response: &Element) -> Option<String> {
+    response.child(ASSERTION_NS, "Issuer")
+        .or_else(|| response.child(ASSERTION_NS, "Assertion")?.child(ASSERTION_NS, "Issuer"))
+        .map(|issuer| issuer.text().trim().to_string())
+}
+
+/// Validate a response. `request_id` is the AuthnRequest it answers, or
+/// None for an IdP-initiated login.
+pub(crate) fn validate_response(
+    config: &SamlConfig,
+    response: &Element,
+    request_id: Option<&str>,
+) -> Result<Assertion, SsoError> {
+    let now = Utc::now();
+    let skew = Duration::seconds(CLOCK_SKEW_SECS);
+
+    if let Some(destination) = response.attribute("Destination") {
+        if destination != config.sp_acs_url {
+            return Err(SsoError::InvalidResponse(format!("sent to {}", destination)));
+        }
+    }
+    if response.attribute("InResponseTo") != request_id {
+        return Err(SsoError::InvalidResponse("InResponseTo does not match the request".to_string()));
+    }
+    if let Some(issuer) = response.child(ASSERTION_NS, "Issuer") {
+        check_issuer(config, issuer)?;
+    }
+
+    let status = response.child(PROTOCOL_NS, "Status")
+        .and_then(|s| s.child(PROTOCOL_NS, "StatusCode"))
+        .and_then(|c| c.attribute("Value"))
+        .unwrap_or_default();
+    if status != STATUS_SUCCESS {
+        let message = response.child(PROTOCOL_NS, "Status")
+            .and_then(|s| s.child(PROTOCOL_NS, "StatusMessage"))
+            .map(|m| m.text())
+            .unwrap_or_default();
+        return Err(SsoError::Idp(format!("{} {}", status.rsplit(':').next().unwrap_or(status), message).trim().to_string()));
+    }
+
+    if response.child(ASSERTION_NS, "EncryptedAssertion").is_some() {
+        return Err(SsoError::InvalidResponse("encrypted assertions are not supported".to_string()));
+    }
+    let mut assertions = response.children_named(ASSERTION_NS, "Assertion");
+    let assertion = assertions.next()
+        .ok_or_else(|| SsoError::InvalidResponse("no assertion".to_string()))?;
+    if assertions.next().is_some() {
+        return Err(SsoError::InvalidResponse("more than one assertion".to_string()));
+    }
+
+    // Only data from the signed element is trusted. IDs must be unique so a
+    // wrapped copy cannot borrow the signature of the original.
+    let certificates = config.certificates()?;
+    let mut ids: Vec<&str> = response.descendants().into_iter().filter_map(|e| e.attribute("ID")).collect();
+    let count = ids.len();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() != count {
+        return Err(SsoError::InvalidResponse("duplicate ID attributes".to_string()));
+    }
+    if assertion.child(DSIG_NS, "Signature").is_some() {
+        verify_signature(assertion, &certificates)?;
+    } else if config.want_assertions_signed {
+        return Err(SsoError::SignatureInvalid);
+    } else if response.child(DSIG_NS, "Signature").is_some() {
+        verify_signature(response, &certificates)?;
+    } else {
+        return Err(SsoError::SignatureInvalid);
+    }
+
+    let issuer = assertion.child(ASSERTION_NS, "Issuer")
+        .ok_or_else(|| SsoError::InvalidResponse("assertion has no issuer".to_string()))?;
+    check_issuer(config, issuer)?;
+    let id = assertion.attribute("ID")
+        .ok_or_else(|| SsoError::InvalidResponse("assertion has no ID".to_string()))?;
+
+    // Subject and its bearer confirmation
+    let subject = assertion.child(ASSERTION_NS, "Subject")
+        .ok_or_else(|| SsoError::InvalidResponse("assertion has no subject".to_string()))?;
+    let name_id = subject.child(ASSERTION_NS, "NameID")
+        .map(|n| n.text().trim().to_string())
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| SsoError::InvalidResponse("subject has no NameID".to_string()))?;
+    let confirmation = subject.children_named(ASSERTION_NS, "SubjectConfirmation")
+        .filter(|c| c.attribute("Method") == Some(BEARER))
+        .filter_map(|c| c.child(ASSERTION_NS, "SubjectConfirmationData"))
+        .find(|data| {
+            data.attribute("Recipient") == Some(config.sp_acs_url.as_str())
+                && data.attribute("InResponseTo") == request_id
+                && time(data.attribute("NotBefore")).is_none_or(|t| t <= now + skew)
+                && time(data.attribute("NotOnOrAfter")).is_some_and(|t| now - skew < t)
+        })
+        .ok_or_else(|| SsoError::InvalidResponse("no valid bearer subject confirmation".to_string()))?;
+
+    // Conditions
+    let mut not_on_or_after = time(confirmation.attribute("NotOnOrAfter")).unwrap_or(now);
+    if let Some(conditions) = assertion.child(ASSERTION_NS, "Conditions") {
+        if time(conditions.attribute("NotBefore")).is_some_and(|t| t > now + skew) {
+            return Err(SsoError::InvalidResponse("assertion not yet valid".to_string()));
+        }
+        if let Some(end) = time(conditions.attribute("NotOnOrAfter")) {
+            if end <= now - skew {
+                return Err(SsoError::TokenExpired);
+            }
+            not_on_or_after = not_on_or_after.max(end);
+        }
+        for restriction in conditions.children_named(ASSERTION_NS, "AudienceRestriction") {
+            let allowed = restriction.children_named(ASSERTION_NS, "Audience")
+                .any(|a| a.text().trim() == config.sp_entity_id);
+            if !allowed {
+                return Err(SsoError::InvalidResponse("assertion is for another audience".to_string()));
+            }
+        }
+    }
+
+    let authn = assertion.child(ASSERTION_NS, "AuthnStatement");
+    let session_not_on_or_after = authn.and_then(|a| time(a.attribute("SessionNotOnOrAfter")));
+    let authn_context = authn
+        .and_then(|a| a.child(ASSERTION_NS, "AuthnContext"))
+        .and_then(|c| c.child(ASSERTION_NS, "AuthnContextClassRef"))
+        .map(|r| r.text().trim().to_string());
+
+    let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+    for statement in assertion.children_named(ASSERTION_NS, "AttributeStatement") {
+        for attribute in statement.children_named(ASSERTION_NS, "Attribute") {
+            let values: Vec<String> = attribute.children_named(ASSERTION_NS, "AttributeValue")
+                .map(|v| v.text().trim().to_string())
+                .collect();
+            for key in [attribute.attribute("Name"), attribute.attribute("FriendlyName")].into_iter().flatten() {
+                attributes.entry(key.to_string()).or_default().extend(values.iter().cloned());
+            }
+        }
+    }
+
+    Ok(Assertion {
+        id: id.to_string(),
+        name_id,
+        not_on_or_after,
+        session_not_on_or_after,
+        authn_context,
+        attributes,
+    })
+}
+
+fn check_issuer(config: &SamlConfig, issuer: &Element) -> Result<(), SsoError> {
+    let issuer = issuer.text();
+    if issuer.trim() != config.idp_entity_id {
+        return Err(SsoError::InvalidResponse(format!("issued by {}", issuer.trim())));
+    }
+    Ok(())
+}
+
+fn time(value: Option<&str>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value?).ok().map(|t| t.with_timezone(&Utc))
+}
+
+// =============================================================================
+// XML signatures
+// =============================================================================
+
+/// Verify the enveloped signature on `signed` with one of the IdP's
+/// certificates. KeyInfo in the message is ignored: trusting it would let
+/// anyone sign.
+fn verify_signature(signed: &Element, certificates: &[Vec<u8>]) -> Result<(), SsoError> {
+    use ring::signature;
+
+    let invalid = |reason: &str| SsoError::InvalidResponse(format!("signature: {}", reason));
+    let sig = signed.child(DSIG_NS, "Signature").ok_or(SsoError::SignatureInvalid)?;
+    let signed_info = sig.child(DSIG_NS, "SignedInfo").ok_or_else(|| invalid("no SignedInfo"))?;
+
+    let c14n = signed_info.child(DSIG_NS, "CanonicalizationMethod").ok_or_else(|| invalid("no CanonicalizationMethod"))?;
+    if c14n.attribute("Algorithm") != Some(EXC_C14N) {
+        return Err(SsoError::UnsupportedAlgorithm(c14n.attribute("Algorithm").unwrap_or_default().to_string()));
+    }
+    let method = signed_info.child(DSIG_NS, "SignatureMethod")
+        .and_then(|m| m.attribute("Algorithm"))
+        .ok_or_else(|| invalid("no SignatureMethod"))?;
+    let algorithm: &'static dyn signature::VerificationAlgorithm = match method {
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+        "http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256" => &signature::ECDSA_P256_SHA256_FIXED,
+        other => return Err(SsoError::UnsupportedAlgorithm(other.to_string())),
+    };
+
+    // Exactly one reference, to the signed element itself
+    let mut references = signed_info.children_named(DSIG_NS, "Reference");
+    let reference = references.next().ok_or_else(|| invalid("no Reference"))?;
+    if references.next().is_some() {
+        return Err(invalid("more than one Reference"));
+    }
+    let id = signed.attribute("ID").ok_or_else(|| invalid("signed element has no ID"))?;
+    if reference.attribute("URI") != Some(format!("#{}", id).as_str()) {
+        return Err(invalid("Reference does not cover the signed element"));
+    }
+
+    let mut enveloped = false;
+    let mut inclusive = Vec::new();
+    for transform in reference.child(DSIG_NS, "Transforms").into_iter().flat_map(|t| t.children_named(DSIG_NS, "Transform")) {
+        match transform.attribute("Algorithm") {
+            Some(ENVELOPED_SIGNATURE) => enveloped = true,
+            Some(EXC_C14N) => inclusive = inclusive_prefixes(transform),
+            other => return Err(SsoError::UnsupportedAlgorithm(other.unwrap_or_default().to_string())),
+        }
+    }
+    if !enveloped {
+        return Err(invalid("not an enveloped signature"));
+    }
+
+    let canonical = xml::canonicalize(signed, Some(sig), &inclusive);
+    let digest = match reference.child(DSIG_NS, "DigestMethod").and_then(|d| d.attribute("Algorithm")) {
+        Some("http://www.w3.org/2001/04/xmlenc#sha256") => Sha256::digest(canonical.as_bytes()).to_vec(),
+        Some("http://www.w3.org/2001/04/xmlenc#sha512") => Sha512::digest(canonical.as_bytes()).to_vec(),
+        other => return Err(SsoError::UnsupportedAlgorithm(other.unwrap_or_default().to_string())),
+    };
+    let expected = reference.child(DSIG_NS, "DigestValue")
+        .and_then(base64_text)
+        .ok_or_else(|| invalid("bad DigestValue"))?;
+    if digest != expected {
+        return Err(SsoError::SignatureInvalid);
+    }
+
+    let signed_info_c14n = xml::canonicalize(signed_info, None, &inclusive_prefixes(c14n));
+    let value = sig.child(DSIG_NS, "SignatureValue")
+        .and_then(base64_text)
+        .ok_or_else(|| invalid("bad SignatureValue"))?;
+
+    let verified = certificates.iter().any(|der| {
+        let Ok((_, certificate)) = x509_parser::parse_x509_certificate(der) else { return false };
+        let key = certificate.public_key().subject_public_key.data.as_ref();
+        signature::UnparsedPublicKey::new(algorithm, key)
+            .verify(signed_info_c14n.as_bytes(), &value)
+            .is_ok()
+    });
+    if verified {
+        Ok(())
+    } else {
+        Err(SsoError::SignatureInvalid)
+    }
+}
+
+/// InclusiveNamespaces PrefixList of an exc-c14n method or transform
+fn inclusive_prefixes(method: &Element) -> Vec<String> {
+    method.child(EXC_C14N, "InclusiveNamespaces")
+        .and_then(|n| n.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+fn base64_text(element: &Element) -> Option<Vec<u8>> {
+    let compact: String = element.text().split_whitespace().collect();
+    base64::engine::general_purpose::STANDARD.decode(compact).ok()
+}
+
+/// DER from a PEM certificate or bare base64
+pub(crate) fn certificate_der(certificate: &str) -> Option<Vec<u8>> {
+    let compact: String = certificate.lines()
+        .filter(|line| !line.starts_with("-----"))
+        .flat_map(|line| line.split_whitespace())
+        .collect();
+    base64::engine::general_purpose::STANDARD.decode(compact).ok()
+}
+
+// =============================================================================
+// Encoding helpers
+// =============================================================================
+
+/// Raw DEFLATE in stored (uncompressed) blocks. Every inflater accepts
+/// them, and AuthnRequests are small enough that compression is moot.
+fn deflate_stored(data: &[u8]) -> Vec<u8> {
+    if data.is_empty() {
+        return vec![0x01, 0x00, 0x00, 0xff, 0xff];
+    }
+    let mut out = Vec::with_capacity(data.len() + 5 * (data.len() / 0xffff + 1));
+    let mut blocks = data.chunks(0xffff).peekable();
+    while let Some(block) = blocks.next() {
+        // BFINAL on the last block, BTYPE 00, padded to the byte boundary
+        out.push(u8::from(blocks.peek().is_none()));
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pki::der::{self, oid};
+    use crate::pki::x509::{self, TbsCertificate};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    const IDP: &str = "https://idp.example.com/metadata";
+    const SP: &str = "https://sase.example.com/saml";
+    const ACS: &str = "https://sase.example.com/saml/acs";
+    const REQUEST: &str = "_req1";
+    /// Where `sign` puts the enveloped signature of the assertion or response
+    const ASSERTION_SIGNATURE: &str = "{assertion-signature}";
+    const RESPONSE_SIGNATURE: &str = "{response-signature}";
+
+    struct Idp {
+        key: EcdsaKeyPair,
+        certificate: Vec<u8>,
+    }
+
+    impl Idp {
+        fn new() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+            // The certificate is only a key carrier; self-signed will do
+            let ca_pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let ca = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, ca_pkcs8.as_ref(), &rng).unwrap();
+            let spki = der::sequence(&[
+                &der::sequence(&[&der::oid(oid::EC_PUBLIC_KEY), &der::oid(oid::P256)]),
+                &der::bit_string(key.public_key().as_ref()),
+            ]);
+            let now = Utc::now();
+            let certificate = TbsCertificate {
+                serial: &[0x01],
+                issuer: &x509::name("IdP Signing", None),
+                subject: &x509::name("IdP Signing", None),
+                not_before: now - Duration::hours(1),
+                not_after: now + Duration::days(1),
+                spki: &spki,
+                extensions: Vec::new(),
+            }.sign(&ca, &rng).unwrap();
+            Self { key, certificate }
+        }
+
+        fn config(&self) -> SamlConfig {
+            SamlConfig {
+                idp_entity_id: IDP.to_string(),
+                idp_sso_url: "https://idp.example.com/sso".to_string(),
+                idp_certificates: vec![base64::engine::general_purpose::STANDARD.encode(&self.certificate)],
+                sp_entity_id: SP.to_string(),
+                sp_acs_url: ACS.to_string(),
+                name_id_format: "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress".to_string(),
+                want_assertions_signed: true,
+                allow_idp_initiated: false,
+            }
+        }
+
+        /// Replace `marker` in `document` with an enveloped signature over
+        /// the element with ID `id`
+        fn sign(&self, document: &str, marker: &str, id: &str) -> String {
+            let template = |digest: &str, value: &str| format!(
+                "<ds:Signature xmlns:ds=\"{DSIG_NS}\"><ds:SignedInfo>\
+                 <ds:CanonicalizationMethod Algorithm=\"{EXC_C14N}\"/>\
+                 <ds:SignatureMethod Algorithm=\"http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256\"/>\
+                 <ds:Reference URI=\"#{id}\"><ds:Transforms>\
+                 <ds:Transform Algorithm=\"{ENVELOPED_SIGNATURE}\"/>\
+                 <ds:Transform Algorithm=\"{EXC_C14N}\"/>\
+                 </ds:Transforms>\
+                 <ds:DigestMethod Algorithm=\"http://www.w3.org/2001/04/xmlenc#sha256\"/>\
+                 <ds:DigestValue>{digest}</ds:DigestValue>\
+                 </ds:Reference></ds:SignedInfo>\
+                 <ds:SignatureValue>{value}</ds:SignatureValue></ds:Signature>"
+            );
+            let signed = |document: &str| {
+                let root = xml::parse(document).unwrap();
+                root.descendants().into_iter()
+                    .find(|e| e.attribute("ID") == Some(id))
+                    .cloned()
+                    .unwrap()
+            };
+            let b64 = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+
+            let unsigned = document.replace(marker, &template("", ""));
+            let element = signed(&unsigned);
+            let sig = element.child(DSIG_NS, "Signature").unwrap();
+            let digest = b64(&Sha256::digest(xml::canonicalize(&element, Some(sig), &[]).as_bytes()));
+
+            let digested = document.replace(marker, &template(&digest, ""));
+            let element = signed(&digested);
+            let signed_info = element.child(DSIG_NS, "Signature").unwrap().child(DSIG_NS, "SignedInfo").unwrap();
+            let value = self.key
+                .sign(&SystemRandom::new(), xml::canonicalize(signed_info, None, &[]).as_bytes())
+                .unwrap();
+            document.replace(marker, &template(&digest, &b64(value.as_ref())))
+        }
+    }
+
+    /// What the IdP asserts
+    struct Claims {
+        id: &'static str,
+        name_id: &'static str,
+        audience: &'static str,
+        recipient: &'static str,
+        in_response_to: &'static str,
+        /// Bearer confirmation validity
+        confirmation_expiry: DateTime<Utc>,
+        /// Conditions validity
+        expiry: DateTime<Utc>,
+    }
+
+    impl Default for Claims {
+        fn default() -> Self {
+            let expiry = Utc::now() + Duration::minutes(5);
+            Self {
+                id: "_a1",
+                name_id: "alice@example.com",
+                audience: SP,
+                recipient: ACS,
+                in_response_to: REQUEST,
+                confirmation_expiry: expiry,
+                expiry,
+            }
+        }
+    }
+
+    fn timestamp(at: DateTime<Utc>) -> String {
+        at.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    /// Assertion with an ASSERTION_SIGNATURE marker after its issuer
+    fn assertion(claims: &Claims) -> String {
+        let now = Utc::now();
+        format!(
+            "<saml:Assertion xmlns:saml=\"{ASSERTION_NS}\" ID=\"{id}\" Version=\"2.0\" IssueInstant=\"{now}\">\
+             <saml:Issuer>{IDP}</saml:Issuer>{ASSERTION_SIGNATURE}\
+             <saml:Subject><saml:NameID>{name_id}</saml:NameID>\
+             <saml:SubjectConfirmation Method=\"{BEARER}\">\
+             <saml:SubjectConfirmationData Recipient=\"{recipient}\" InResponseTo=\"{in_response_to}\" NotOnOrAfter=\"{confirmation_expiry}\"/>\
+             </saml:SubjectConfirmation></saml:Subject>\
+             <saml:Conditions NotBefore=\"{not_before}\" NotOnOrAfter=\"{expiry}\">\
+             <saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience></saml:AudienceRestriction>\
+             </saml:Conditions>\
+             <saml:AttributeStatement>\
+             <saml:Attribute Name=\"urn:oid:1.3.6.1.4.1.5923.1.5.1.1\" FriendlyName=\"groups\">\
+             <saml:AttributeValue>engineering</saml:AttributeValue></saml:Attribute>\
+             </saml:AttributeStatement></saml:Assertion>",
+            id = claims.id,
+            now = timestamp(now),
+            name_id = claims.name_id,
+            recipient = claims.recipient,
+            in_response_to = claims.in_response_to,
+            confirmation_expiry = timestamp(claims.confirmation_expiry),
+            not_before = timestamp(now - Duration::minutes(1)),
+            expiry = timestamp(claims.expiry),
+            audience = claims.audience,
+        )
+    }
+
+    /// Response around `assertions`, with a RESPONSE_SIGNATURE marker
+    fn response(assertions: &str) -> String {
+        format!(
+            "<samlp:Response xmlns:samlp=\"{PROTOCOL_NS}\" xmlns:saml=\"{ASSERTION_NS}\" ID=\"_r1\" Version=\"2.0\" \
+             IssueInstant=\"{now}\" Destination=\"{ACS}\" InResponseTo=\"{REQUEST}\">\
+             <saml:Issuer>{IDP}</saml:Issuer>{RESPONSE_SIGNATURE}\
+             <samlp:Status><samlp:StatusCode Value=\"{STATUS_SUCCESS}\"/></samlp:Status>\
+             {assertions}</samlp:Response>",
+            now = timestamp(Utc::now()),
+        )
+    }
+
+    /// Response carrying the assertion for `claims`, signed by the IdP
+    fn signed_response(idp: &Idp, claims: &Claims) -> String {
+        let assertion = idp.sign(&assertion(claims), ASSERTION_SIGNATURE, claims.id);
+        response(&assertion).replace(RESPONSE_SIGNATURE, "")
+    }
+
+    fn validate(config: &SamlConfig, document: &str, request_id: Option<&str>) -> Result<Assertion, SsoError> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(document);
+        validate_response(config, &decode_response(&encoded)?, request_id)
+    }
+
+    #[test]
+    fn test_signed_assertion_is_accepted() {
+        let idp = Idp::new();
+        let document = signed_response(&idp, &Claims::default());
+
+        let assertion = validate(&idp.config(), &document, Some(REQUEST)).unwrap();
+        assert_eq!(assertion.id, "_a1");
+        assert_eq!(assertion.name_id, "alice@example.com");
+        assert_eq!(assertion.attributes["groups"], vec!["engineering".to_string()]);
+        assert_eq!(response_issuer(&decode_response(
+            &base64::engine::general_purpose::STANDARD.encode(&document),
+        ).unwrap()).as_deref(), Some(IDP));
+    }
+
+    #[test]
+    fn test_signed_response_is_accepted_when_assertions_need_not_be_signed() {
+        let idp = Idp::new();
+        let unsigned = assertion(&Claims::default()).replace(ASSERTION_SIGNATURE, "");
+        let document = idp.sign(&response(&unsigned), RESPONSE_SIGNATURE, "_r1");
+
+        let config = SamlConfig { want_assertions_signed: false, ..idp.config() };
+        assert_eq!(validate(&config, &document, Some(REQUEST)).unwrap().name_id, "alice@example.com");
+    }
+
+    #[test]
+    fn test_unsigned_assertion_in_signed_response_is_rejected() {
+        let idp = Idp::new();
+        let unsigned = assertion(&Claims::default()).replace(ASSERTION_SIGNATURE, "");
+        let document = idp.sign(&response(&unsigned), RESPONSE_SIGNATURE, "_r1");
+
+        let result = validate(&idp.config(), &document, Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::SignatureInvalid)));
+    }
+
+    #[test]
+    fn test_signature_from_another_key_is_rejected() {
+        let idp = Idp::new();
+        let document = signed_response(&Idp::new(), &Claims::default());
+
+        let result = validate(&idp.config(), &document, Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::SignatureInvalid)));
+    }
+
+    #[test]
+    fn test_tampered_name_id_is_rejected() {
+        let idp = Idp::new();
+        let document = signed_response(&idp, &Claims::default())
+            .replace("alice@example.com", "admin@example.com");
+
+        let result = validate(&idp.config(), &document, Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::SignatureInvalid)));
+    }
+
+    #[test]
+    fn test_wrapped_assertion_is_rejected() {
+        let idp = Idp::new();
+        let original = idp.sign(&assertion(&Claims::default()), ASSERTION_SIGNATURE, "_a1");
+        let forged = original.replace("alice@example.com", "admin@example.com");
+        let config = idp.config();
+
+        // Signed original moved into Extensions, forged copy reusing its ID
+        let moved = response(&format!("<samlp:Extensions>{}</samlp:Extensions>{}", original, forged))
+            .replace(RESPONSE_SIGNATURE, "");
+        assert!(validate(&config, &moved, Some(REQUEST)).is_err());
+
+        // Forged copy under a fresh ID, still carrying the original signature
+        let renamed = forged.replace("ID=\"_a1\"", "ID=\"_a2\"");
+        let moved = response(&format!("<samlp:Extensions>{}</samlp:Extensions>{}", original, renamed))
+            .replace(RESPONSE_SIGNATURE, "");
+        assert!(validate(&config, &moved, Some(REQUEST)).is_err());
+
+        // Signed original next to the forged one
+        let duplicated = response(&format!("{}{}", renamed, original)).replace(RESPONSE_SIGNATURE, "");
+        assert!(validate(&config, &duplicated, Some(REQUEST)).is_err());
+    }
+
+    #[test]
+    fn test_comment_in_name_id_does_not_truncate_it() {
+        // Comments are outside the signature, so the IdP-signed name can
+        // be split by one; it must still be read whole
+        let idp = Idp::new();
+        let claims = Claims { name_id: "admin@example.com.evil.test", ..Claims::default() };
+        let document = signed_response(&idp, &claims)
+            .replace("admin@example.com.evil.test", "admin@example.com<!---->.evil.test");
+
+        let assertion = validate(&idp.config(), &document, Some(REQUEST)).unwrap();
+        assert_eq!(assertion.name_id, "admin@example.com.evil.test");
+    }
+
+    #[test]
+    fn test_wrong_audience_or_recipient_is_rejected() {
+        let idp = Idp::new();
+        let config = idp.config();
+
+        let audience = Claims { audience: "https://other.example.com/saml", ..Claims::default() };
+        let result = validate(&config, &signed_response(&idp, &audience), Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::InvalidResponse(_))));
+
+        let recipient = Claims { recipient: "https://other.example.com/saml/acs", ..Claims::default() };
+        let result = validate(&config, &signed_response(&idp, &recipient), Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn test_expired_assertion_is_rejected() {
+        let idp = Idp::new();
+        let config = idp.config();
+        let past = Utc::now() - Duration::hours(1);
+
+        let expired = Claims { expiry: past, ..Claims::default() };
+        let result = validate(&config, &signed_response(&idp, &expired), Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::TokenExpired)));
+
+        let confirmation = Claims { confirmation_expiry: past, ..Claims::default() };
+        let result = validate(&config, &signed_response(&idp, &confirmation), Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn test_in_response_to_mismatch_is_rejected() {
+        let idp = Idp::new();
+        let config = idp.config();
+        let document = signed_response(&idp, &Claims::default());
+
+        assert!(matches!(validate(&config, &document, Some("_req2")), Err(SsoError::InvalidResponse(_))));
+        assert!(matches!(validate(&config, &document, None), Err(SsoError::InvalidResponse(_))));
+
+        // Response answers the request but the assertion does not
+        let stale = Claims { in_response_to: "_req0", ..Claims::default() };
+        let result = validate(&config, &signed_response(&idp, &stale), Some(REQUEST));
+        assert!(matches!(result, Err(SsoError::InvalidResponse(_))));
+    }
+}
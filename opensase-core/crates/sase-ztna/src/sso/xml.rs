@@ -0,0 +1,488 @@
+//! Minimal XML for SAML
+//!
+//! Just enough XML 1.0 to read SAML protocol messages and metadata and to
+//! verify their signatures: a namespace-aware element tree and Exclusive
+//! XML Canonicalization 1.0 without comments. Documents with a DTD are
+//! refused, which rules out entity expansion and external entities.
+
+use std::collections::BTreeMap;
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+/// Nesting limit; SAML responses are a handful of levels deep
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Element {
+    /// Prefix as written, empty for the default namespace
+    pub prefix: String,
+    pub name: String,
+    /// Namespace URI, empty when in no namespace
+    pub namespace: String,
+    pub attributes: Vec<Attribute>,
+    pub children: Vec<Node>,
+    /// Namespaces in scope here, including inherited ones, by prefix
+    /// (empty for the default namespace)
+    scope: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Attribute {
+    pub prefix: String,
+    pub name: String,
+    pub namespace: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct XmlError(pub String);
+
+impl std::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "XML error: {}", self.0)
+    }
+}
+
+impl Element {
+    /// Unqualified attribute value
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|a| a.namespace.is_empty() && a.name == name)
+            .map(|a| a.value.as_str())
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    pub fn children_named<'a>(&'a self, namespace: &'a str, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |e| e.is(namespace, name))
+    }
+
+    pub fn child(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.is(namespace, name))
+    }
+
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    /// Concatenated text content of this element and its descendants
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text
+    }
+
+    fn collect_text(&self, out: &mut String) {
+        for node in &self.children {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Element(element) => element.collect_text(out),
+            }
+        }
+    }
+
+    /// This element and all descendants, depth first
+    pub fn descendants(&self) -> Vec<&Element> {
+        let mut found = vec![self];
+        let mut index = 0;
+        while index < found.len() {
+            let element = found[index];
+            found.extend(element.elements());
+            index += 1;
+        }
+        found
+    }
+
+    fn qualified_name(&self) -> String {
+        qualified(&self.prefix, &self.name)
+    }
+}
+
+fn qualified(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}:{}", prefix, name)
+    }
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+/// Parse a document, returning its root element
+pub(crate) fn parse(input: &str) -> Result<Element, XmlError> {
+    // Line-end normalisation (XML 1.0 §2.11)
+    let input = input.replace("\r\n", "\n").replace('\r', "\n");
+    let mut parser = Parser { input: input.trim_start_matches('\u{feff}'), pos: 0 };
+
+    parser.skip_misc()?;
+    let mut scope = BTreeMap::new();
+    scope.insert("xml".to_string(), XML_NS.to_string());
+    let root = parser.element(&scope, 0)?;
+    parser.skip_misc()?;
+    if parser.pos < parser.input.len() {
+        return Err(XmlError("content after the root element".to_string()));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n']).len();
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        let end = rest.find(terminator)
+            .ok_or_else(|| XmlError(format!("unterminated construct, expected {}", terminator)))?;
+        self.pos += end + terminator.len();
+        Ok(&rest[..end])
+    }
+
+    /// XML declaration, comments, processing instructions and whitespace
+    /// outside the root element
+    fn skip_misc(&mut self) -> Result<(), XmlError> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!") {
+                return Err(XmlError("DTDs are not allowed".to_string()));
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=')).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(XmlError("expected a name".to_string()));
+        }
+        self.pos += end;
+        Ok(&rest[..end])
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), XmlError> {
+        if !self.rest().starts_with(token) {
+            return Err(XmlError(format!("expected {}", token)));
+        }
+        self.pos += token.len();
+        Ok(())
+    }
+
+    fn element(&mut self, parent_scope: &BTreeMap<String, String>, depth: usize) -> Result<Element, XmlError> {
+        if depth > MAX_DEPTH {
+            return Err(XmlError("document nested too deeply".to_string()));
+        }
+        self.expect("<")?;
+        let qname = self.name()?;
+
+        // Raw attributes first: declarations anywhere in the start tag
+        // apply to the element and all its attributes
+        let mut raw: Vec<(&str, String)> = Vec::new();
+        let empty = loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                break true;
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break false;
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = self.rest().chars().next().filter(|c| *c == '"' || *c == '\'')
+                .ok_or_else(|| XmlError(format!("unquoted value for {}", name)))?;
+            self.pos += 1;
+            let value = self.skip_past(if quote == '"' { "\"" } else { "'" })?;
+            if value.contains('<') {
+                return Err(XmlError(format!("'<' in value of {}", name)));
+            }
+            if raw.iter().any(|(n, _)| *n == name) {
+                return Err(XmlError(format!("duplicate attribute {}", name)));
+            }
+            // Attribute-value normalisation: literal whitespace becomes a space
+            raw.push((name, decode(&value.replace(['\t', '\n'], " "))?));
+        };
+
+        let mut scope = parent_scope.clone();
+        for (name, value) in &raw {
+            if *name == "xmlns" {
+                scope.insert(String::new(), value.clone());
+            } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+                if value.is_empty() {
+                    return Err(XmlError(format!("prefix {} bound to an empty namespace", prefix)));
+                }
+                scope.insert(prefix.to_string(), value.clone());
+            }
+        }
+
+        let (prefix, name) = split(qname);
+        let namespace = resolve(&scope, prefix, true)?;
+        let mut attributes = Vec::new();
+        for (raw_name, value) in raw {
+            if raw_name == "xmlns" || raw_name.starts_with("xmlns:") {
+                continue;
+            }
+            let (attr_prefix, attr_name) = split(raw_name);
+            attributes.push(Attribute {
+                prefix: attr_prefix.to_string(),
+                name: attr_name.to_string(),
+                // Unprefixed attributes are in no namespace, whatever the default
+                namespace: resolve(&scope, attr_prefix, false)?,
+                value,
+            });
+        }
+
+        let mut element = Element {
+            prefix: prefix.to_string(),
+            name: name.to_string(),
+            namespace,
+            attributes,
+            children: Vec::new(),
+            scope,
+        };
+        if empty {
+            return Ok(element);
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(XmlError(format!("unclosed element {}", qname)));
+            }
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let closing = self.name()?;
+                if closing != qname {
+                    return Err(XmlError(format!("</{}> closes <{}>", closing, qname)));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let text = self.skip_past("]]>")?;
+                push_text(&mut element.children, text.to_string());
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!") {
+                return Err(XmlError("markup declarations are not allowed".to_string()));
+            } else if rest.starts_with('<') {
+                let child = self.element(&element.scope, depth + 1)?;
+                element.children.push(Node::Element(child));
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                push_text(&mut element.children, decode(&rest[..end])?);
+            }
+        }
+    }
+}
+
+/// Merge adjacent text, as from CDATA next to character data
+fn push_text(children: &mut Vec<Node>, text: String) {
+    if let Some(Node::Text(previous)) = children.last_mut() {
+        previous.push_str(&text);
+    } else if !text.is_empty() {
+        children.push(Node::Text(text));
+    }
+}
+
+fn split(qname: &str) -> (&str, &str) {
+    qname.split_once(':').unwrap_or(("", qname))
+}
+
+fn resolve(scope: &BTreeMap<String, String>, prefix: &str, use_default: bool) -> Result<String, XmlError> {
+    if prefix.is_empty() && !use_default {
+        return Ok(String::new());
+    }
+    match scope.get(prefix) {
+        Some(namespace) => Ok(namespace.clone()),
+        None if prefix.is_empty() => Ok(String::new()),
+        None => Err(XmlError(format!("undeclared namespace prefix {}", prefix))),
+    }
+}
+
+/// Replace the predefined entities and character references
+fn decode(raw: &str) -> Result<String, XmlError> {
+    if !raw.contains('&') {
+        return Ok(raw.to_string());
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find(';')
+            .ok_or_else(|| XmlError("unterminated entity reference".to_string()))?;
+        let entity = &rest[start + 1..start + end];
+        let decoded = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| XmlError(format!("unknown entity &{};", entity)))?
+            }
+        };
+        out.push(decoded);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Escape text for inclusion in a document we generate
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// =============================================================================
+// Exclusive canonicalisation
+// =============================================================================
+
+/// Exclusive XML Canonicalization 1.0 (omitting comments) of `element`,
+/// leaving out `exclude` (the enveloped signature, for reference digests).
+/// `inclusive` lists the InclusiveNamespaces PrefixList, `#default`
+/// standing for the default namespace.
+pub(crate) fn canonicalize(element: &Element, exclude: Option<&Element>, inclusive: &[String]) -> String {
+    let inclusive: Vec<&str> = inclusive.iter()
+        .map(|p| if p == "#default" { "" } else { p.as_str() })
+        .collect();
+    let mut out = String::new();
+    write_canonical(element, &BTreeMap::new(), exclude, &inclusive, &mut out);
+    out
+}
+
+fn write_canonical(
+    element: &Element,
+    rendered: &BTreeMap<String, String>,
+    exclude: Option<&Element>,
+    inclusive: &[&str],
+    out: &mut String,
+) {
+    // Namespaces visibly utilised here, plus the inclusive prefixes, that
+    // the nearest output ancestor did not already declare the same way
+    let mut utilised: Vec<&str> = vec![element.prefix.as_str()];
+    utilised.extend(element.attributes.iter().map(|a| a.prefix.as_str()).filter(|p| !p.is_empty()));
+    utilised.extend(inclusive.iter().copied());
+
+    let mut declarations: BTreeMap<&str, &str> = BTreeMap::new();
+    for prefix in utilised {
+        if prefix == "xml" {
+            continue;
+        }
+        let namespace = element.scope.get(prefix).map(String::as_str).unwrap_or("");
+        let current = rendered.get(prefix).map(String::as_str).unwrap_or("");
+        // An empty non-default namespace is simply out of scope; an empty
+        // default one is the xmlns="" undeclaration
+        if namespace != current && (prefix.is_empty() || !namespace.is_empty()) {
+            declarations.insert(prefix, namespace);
+        }
+    }
+
+    let qname = element.qualified_name();
+    out.push('<');
+    out.push_str(&qname);
+    for (prefix, namespace) in &declarations {
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        out.push_str(&escape_attribute(namespace));
+        out.push('"');
+    }
+
+    let mut attributes: Vec<&Attribute> = element.attributes.iter().collect();
+    attributes.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+    for attribute in attributes {
+        out.push(' ');
+        out.push_str(&qualified(&attribute.prefix, &attribute.name));
+        out.push_str("=\"");
+        out.push_str(&escape_attribute(&attribute.value));
+        out.push('"');
+    }
+    out.push('>');
+
+    let mut scope = rendered.clone();
+    for (prefix, namespace) in declarations {
+        scope.insert(prefix.to_string(), namespace.to_string());
+    }
+    for node in &element.children {
+        match node {
+            Node::Text(text) => out.push_str(&escape_text(text)),
+            Node::Element(child) => {
+                if exclude.is_some_and(|excluded| std::ptr::eq(excluded, child)) {
+                    continue;
+                }
+                write_canonical(child, &scope, exclude, inclusive, out);
+            }
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(&qname);
+    out.push('>');
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\r', "&#xD;")
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+        .replace('\t', "&#x9;")
+        .replace('\n', "&#xA;")
+        .replace('\r', "&#xD;")
+}
@@ -1,20 +1,100 @@
 //! Step-Up Authentication
 //!
 //! Mid-session authentication step-up for sensitive operations.
+//!
+//! When continuous evaluation raises a session's risk it marks the session
+//! as requiring step-up instead of ending it. The challenge is issued
+//! inline at the session's next request through [`StepUpManager::enforce`],
+//! which the gateway and the clientless portal call on every request:
+//!
+//! ```text
+//! Clear ──require──► Required ──enforce──► Challenged ──verify ok──► Satisfied
+//!   ▲                    ▲                     │  │                     │
+//!   │                    └──── expired ────────┘  └─ attempts used ─► Failed
+//!   └──────────────────────── grace period over ──────────────────────┘
+//! ```
+//!
+//! A failed step-up ends the session.
 
-use crate::{Session, mfa::{MfaFactorType, MfaChallenge}};
+use crate::{AccessCondition, Session, mfa::{MfaEngine, MfaChallenge}};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use std::sync::Arc;
 
 /// Step-up authentication manager
 pub struct StepUpManager {
     challenges: dashmap::DashMap<String, StepUpChallenge>,
     pending_sessions: dashmap::DashMap<String, String>, // session_id -> challenge_id
+    /// Step-up state by session
+    states: dashmap::DashMap<String, StepUpState>,
+    /// Verifies MFA and biometric step-ups against registered factors
+    mfa: Option<Arc<MfaEngine>>,
+    /// Verifies device re-attestation
+    attestor: Option<Arc<dyn DeviceAttestor>>,
+    /// How long a passed step-up holds off further challenges
+    grace: chrono::Duration,
+}
+
+/// Verifies a device's re-attestation response
+#[async_trait::async_trait]
+pub trait DeviceAttestor: Send + Sync {
+    /// Whether `response` attests `device_id` for the challenge `nonce`
+    async fn attest(&self, device_id: &str, nonce: &str, response: &str) -> bool;
+}
+
+/// Re-attestation with the device certificate: the response is the
+/// base64 DER certificate presented on the client's TLS connection, which
+/// already proves possession of the key.
+#[async_trait::async_trait]
+impl DeviceAttestor for crate::pki::DeviceCa {
+    async fn attest(&self, device_id: &str, _nonce: &str, response: &str) -> bool {
+        let Ok(certificate) = base64::engine::general_purpose::STANDARD.decode(response.trim()) else {
+            return false;
+        };
+        self.verify_peer(&certificate).is_ok_and(|identity| identity.device_id == device_id)
+    }
+}
+
+/// Where a session stands with mid-session step-up
+#[derive(Debug, Clone)]
+pub enum StepUpState {
+    /// Nothing outstanding
+    Clear,
+    /// Risk rose; challenge at the session's next request
+    Required {
+        reason: StepUpReason,
+        challenge_type: ChallengeType,
+        risk_score: f64,
+        since: DateTime<Utc>,
+    },
+    /// Challenge issued, awaiting the user's response
+    Challenged { challenge_id: String, challenge_type: ChallengeType },
+    /// Step-up passed; requirements no stronger than this are waived
+    /// until `until`
+    Satisfied { challenge_type: ChallengeType, until: DateTime<Utc> },
+    /// Step-up failed; the session must end
+    Failed { reason: String },
+}
+
+/// What an enforcement point does with a request
+#[derive(Debug, Clone)]
+pub enum StepUpGate {
+    /// Let the request through
+    Proceed,
+    /// Hold the request until this challenge is answered
+    Challenge(Box<StepUpChallenge>),
+    /// End the session
+    Terminate(String),
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct StepUpChallenge {
     pub id: String,
     pub session_id: String,
     pub user_id: String,
+    /// Device to re-attest
+    pub device_id: String,
     pub reason: StepUpReason,
     pub challenge_type: ChallengeType,
     pub created_at: chrono::DateTime<chrono::Utc>,
@@ -22,9 +102,14 @@ pub struct StepUpChallenge {
     pub status: ChallengeStatus,
     pub attempts: u32,
     pub max_attempts: u32,
+    /// Backing MFA challenge; for WebAuthn its metadata carries the
+    /// assertion options for the client
+    pub mfa_challenge: Option<MfaChallenge>,
+    /// Nonce for device re-attestation
+    pub nonce: Option<String>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StepUpReason {
     SensitiveResource,
     TrustDegradation,
@@ -34,16 +119,40 @@ pub enum StepUpReason {
     AdminForced,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChallengeType {
     Mfa,
     Biometric,
     ReAuth,
     ManagerApproval,
     Custom,
+    /// Device proves it is still enrolled and trusted
+    DeviceAttestation,
+}
+
+impl ChallengeType {
+    /// Relative assurance, for deciding whether one step-up covers another
+    fn strength(&self) -> u8 {
+        match self {
+            Self::Custom | Self::ManagerApproval => 0,
+            Self::Mfa => 1,
+            Self::ReAuth => 2,
+            Self::Biometric | Self::DeviceAttestation => 3,
+        }
+    }
+    
+    /// Access condition the client must satisfy
+    pub fn condition(&self) -> AccessCondition {
+        match self {
+            Self::Biometric => AccessCondition::RequirePhishingResistantMfa,
+            Self::DeviceAttestation => AccessCondition::RequireDeviceCompliance,
+            Self::ManagerApproval => AccessCondition::RequireApproval { approver: "manager".to_string() },
+            Self::Mfa | Self::ReAuth | Self::Custom => AccessCondition::RequireMfa,
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChallengeStatus {
     Pending,
     Completed,
@@ -57,9 +166,32 @@ impl StepUpManager {
         Self {
             challenges: dashmap::DashMap::new(),
             pending_sessions: dashmap::DashMap::new(),
+            states: dashmap::DashMap::new(),
+            mfa: None,
+            attestor: None,
+            grace: chrono::Duration::minutes(15),
         }
     }
     
+    /// Verify MFA and biometric step-ups with the user's registered factors
+    pub fn with_mfa(mut self, mfa: Arc<MfaEngine>) -> Self {
+        self.mfa = Some(mfa);
+        self
+    }
+    
+    /// Verify device re-attestation challenges
+    pub fn with_attestor(mut self, attestor: Arc<dyn DeviceAttestor>) -> Self {
+        self.attestor = Some(attestor);
+        self
+    }
+    
+    /// How long a passed step-up waives further requirements of the same
+    /// or lower strength
+    pub fn with_grace(mut self, grace: chrono::Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+    
     /// Create step-up challenge for session
     pub async fn create_challenge(
         &self,
@@ -75,11 +207,46 @@ impl StepUpManager {
             StepUpReason::PolicyRequired => ChallengeType::Mfa,
             StepUpReason::AdminForced => ChallengeType::ReAuth,
         };
+        self.issue(session, reason, challenge_type).await
+    }
+    
+    async fn issue(
+        &self,
+        session: &Session,
+        reason: StepUpReason,
+        challenge_type: ChallengeType,
+    ) -> StepUpChallenge {
+        // Biometric step-up is a user-verifying passkey; MFA takes the
+        // strongest factor the user has
+        let mfa_challenge = match (&self.mfa, challenge_type) {
+            (Some(mfa), ChallengeType::Mfa | ChallengeType::Biometric) => {
+                let condition = if challenge_type == ChallengeType::Biometric {
+                    AccessCondition::RequirePhishingResistantMfa
+                } else {
+                    AccessCondition::RequireMfa
+                };
+                match mfa.challenge_for_condition(&session.identity.user_id, &condition).await {
+                    Ok(challenge) => Some(challenge),
+                    Err(e) => {
+                        tracing::debug!("No MFA factor for step-up of {}: {}", session.identity.user_id, e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        
+        let nonce = (challenge_type == ChallengeType::DeviceAttestation).then(|| {
+            let mut bytes = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+        });
         
         let challenge = StepUpChallenge {
             id: uuid::Uuid::new_v4().to_string(),
             session_id: session.id.clone(),
             user_id: session.identity.user_id.clone(),
+            device_id: session.device.id.clone(),
             reason,
             challenge_type,
             created_at: chrono::Utc::now(),
@@ -87,6 +254,8 @@ impl StepUpManager {
             status: ChallengeStatus::Pending,
             attempts: 0,
             max_attempts: 3,
+            mfa_challenge,
+            nonce,
         };
         
         // Store challenge
@@ -101,6 +270,114 @@ impl StepUpManager {
         challenge
     }
     
+    /// Require step-up at the session's next request. A requirement no
+    /// stronger than one already outstanding, or than a step-up passed
+    /// within the grace period, changes nothing.
+    pub fn require(
+        &self,
+        session_id: &str,
+        reason: StepUpReason,
+        challenge_type: ChallengeType,
+        risk_score: f64,
+    ) -> StepUpState {
+        let now = Utc::now();
+        let mut state = self.states.entry(session_id.to_string()).or_insert(StepUpState::Clear);
+        
+        let replace = match &*state {
+            StepUpState::Clear => true,
+            StepUpState::Failed { .. } => false,
+            StepUpState::Satisfied { challenge_type: passed, until } => {
+                *until <= now || challenge_type.strength() > passed.strength()
+            }
+            StepUpState::Required { challenge_type: pending, .. }
+            | StepUpState::Challenged { challenge_type: pending, .. } => {
+                challenge_type.strength() > pending.strength()
+            }
+        };
+        if replace {
+            // A stronger requirement supersedes the outstanding challenge
+            if let StepUpState::Challenged { challenge_id, .. } = &*state {
+                if let Some(mut challenge) = self.challenges.get_mut(challenge_id) {
+                    challenge.status = ChallengeStatus::Cancelled;
+                }
+                self.pending_sessions.remove(session_id);
+            }
+            *state = StepUpState::Required { reason, challenge_type, risk_score, since: now };
+            tracing::info!(
+                "Session {} requires {:?} step-up at its next request (reason: {:?}, risk {:.1})",
+                session_id, challenge_type, reason, risk_score
+            );
+        }
+        state.clone()
+    }
+    
+    /// Current step-up state of a session
+    pub fn state(&self, session_id: &str) -> StepUpState {
+        self.states.get(session_id)
+            .map(|s| s.clone())
+            .unwrap_or(StepUpState::Clear)
+    }
+    
+    /// Enforcement hook for each request on a session: issues the challenge
+    /// for a pending requirement and holds the request until it is answered
+    pub async fn enforce(&self, session: &Session) -> StepUpGate {
+        let now = Utc::now();
+        let state = self.state(&session.id);
+        
+        let (reason, challenge_type) = match state {
+            StepUpState::Clear => return StepUpGate::Proceed,
+            StepUpState::Satisfied { until, .. } => {
+                if until <= now {
+                    self.states.remove(&session.id);
+                }
+                return StepUpGate::Proceed;
+            }
+            StepUpState::Failed { reason } => return StepUpGate::Terminate(reason),
+            StepUpState::Required { reason, challenge_type, .. } => (reason, challenge_type),
+            StepUpState::Challenged { challenge_id, challenge_type } => {
+                match self.challenges.get(&challenge_id).map(|c| c.clone()) {
+                    Some(challenge) if challenge.status == ChallengeStatus::Pending && challenge.expires_at > now => {
+                        return StepUpGate::Challenge(Box::new(challenge));
+                    }
+                    Some(challenge) if challenge.status == ChallengeStatus::Failed => {
+                        return self.fail(&session.id, "Step-up verification failed");
+                    }
+                    // Expired or cancelled: issue a fresh one
+                    Some(challenge) => (challenge.reason, challenge_type),
+                    None => (StepUpReason::TrustDegradation, challenge_type),
+                }
+            }
+        };
+        
+        let challenge = self.issue(session, reason, challenge_type).await;
+        self.states.insert(session.id.clone(), StepUpState::Challenged {
+            challenge_id: challenge.id.clone(),
+            challenge_type,
+        });
+        StepUpGate::Challenge(Box::new(challenge))
+    }
+    
+    fn fail(&self, session_id: &str, reason: &str) -> StepUpGate {
+        self.states.insert(session_id.to_string(), StepUpState::Failed { reason: reason.to_string() });
+        self.pending_sessions.remove(session_id);
+        StepUpGate::Terminate(reason.to_string())
+    }
+    
+    /// Forget a session's step-up state when it ends
+    pub fn end_session(&self, session_id: &str) {
+        self.states.remove(session_id);
+        if let Some((_, challenge_id)) = self.pending_sessions.remove(session_id) {
+            if let Some(mut challenge) = self.challenges.get_mut(&challenge_id) {
+                challenge.status = ChallengeStatus::Cancelled;
+            }
+        }
+    }
+    
+    /// Get challenge
+    pub fn challenge(&self, challenge_id: &str) -> Option<StepUpChallenge> {
+        self.challenges.get(challenge_id).map(|c| c.clone())
+    }
+    
     /// Check if session has pending challenge
     pub fn has_pending_challenge(&self, session_id: &str) -> bool {
         self.pending_sessions.contains_key(session_id)
@@ -117,10 +394,51 @@ impl StepUpManager {
         &self,
         challenge_id: &str,
         response: &str,
+    ) -> Result<StepUpResult, StepUpError> {
+        let result = self.verify_challenge(challenge_id, response).await;
+        let Some(challenge) = self.challenges.get(challenge_id).map(|c| c.clone()) else {
+            return result;
+        };
+        
+        // Advance the session's state if this is its outstanding challenge
+        let outstanding = matches!(
+            self.states.get(&challenge.session_id).as_deref(),
+            Some(StepUpState::Challenged { challenge_id: id, .. }) if id == challenge_id
+        );
+        if outstanding {
+            match (&result, challenge.status) {
+                (Ok(_), _) => {
+                    self.states.insert(challenge.session_id.clone(), StepUpState::Satisfied {
+                        challenge_type: challenge.challenge_type,
+                        until: Utc::now() + self.grace,
+                    });
+                }
+                (Err(_), ChallengeStatus::Failed) => {
+                    self.fail(&challenge.session_id, "Step-up verification failed");
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+    
+    async fn verify_challenge(
+        &self,
+        challenge_id: &str,
+        response: &str,
     ) -> Result<StepUpResult, StepUpError> {
         let mut challenge = self.challenges.get_mut(challenge_id)
             .ok_or(StepUpError::ChallengeNotFound)?;
         
+        // Answered, failed and superseded challenges cannot be replayed
+        match challenge.status {
+            ChallengeStatus::Pending => {}
+            ChallengeStatus::Completed => return Err(StepUpError::AlreadyCompleted),
+            ChallengeStatus::Failed => return Err(StepUpError::TooManyAttempts),
+            ChallengeStatus::Expired => return Err(StepUpError::Expired),
+            ChallengeStatus::Cancelled => return Err(StepUpError::ChallengeNotFound),
+        }
+        
         // Check expiration
         if chrono::Utc::now() > challenge.expires_at {
             challenge.status = ChallengeStatus::Expired;
@@ -135,12 +453,37 @@ impl StepUpManager {
         }
         
         // Verify based on challenge type
-        let verified = match challenge.challenge_type {
-            ChallengeType::Mfa => self.verify_mfa(response).await,
-            ChallengeType::Biometric => self.verify_biometric(response).await,
-            ChallengeType::ReAuth => self.verify_reauth(response).await,
-            ChallengeType::ManagerApproval => self.verify_approval(response).await,
-            ChallengeType::Custom => true,
+        let mut phishing_resistant = false;
+        let verified = match (challenge.mfa_challenge.clone(), &self.mfa) {
+            (Some(mfa_challenge), Some(mfa)) => {
+                let result = mfa.verify(&mfa_challenge.id, response).await;
+                phishing_resistant = result.phishing_resistant;
+                let verified = result.success
+                    && (challenge.challenge_type != ChallengeType::Biometric || result.user_verified);
+                
+                // MFA challenges are single-use; issue a fresh one for the next attempt
+                if !verified && challenge.attempts < challenge.max_attempts {
+                    let condition = if challenge.challenge_type == ChallengeType::Biometric {
+                        AccessCondition::RequirePhishingResistantMfa
+                    } else {
+                        AccessCondition::RequireMfa
+                    };
+                    challenge.mfa_challenge = mfa.challenge_for_condition(&challenge.user_id, &condition).await.ok();
+                }
+                verified
+            }
+            _ => match challenge.challenge_type {
+                // Fails closed without an attestor
+                ChallengeType::DeviceAttestation => match (&self.attestor, &challenge.nonce) {
+                    (Some(attestor), Some(nonce)) => attestor.attest(&challenge.device_id, nonce, response).await,
+                    _ => false,
+                },
+                ChallengeType::Mfa => self.verify_mfa(response).await,
+                ChallengeType::Biometric => self.verify_biometric(response).await,
+                ChallengeType::ReAuth => self.verify_reauth(response).await,
+                ChallengeType::ManagerApproval => self.verify_approval(response).await,
+                ChallengeType::Custom => true,
+            },
         };
         
         if verified {
@@ -156,7 +499,8 @@ impl StepUpManager {
                 challenge_id: challenge_id.to_string(),
                 session_id: challenge.session_id.clone(),
                 verified: true,
-                trust_bonus: self.calculate_trust_bonus(&challenge),
+                trust_bonus: self.calculate_trust_bonus(&challenge, phishing_resistant),
+                phishing_resistant,
             })
         } else {
             if challenge.attempts >= challenge.max_attempts {
@@ -186,14 +530,16 @@ impl StepUpManager {
         response == "approved"
     }
     
-    fn calculate_trust_bonus(&self, challenge: &StepUpChallenge) -> f64 {
-        match challenge.challenge_type {
+    fn calculate_trust_bonus(&self, challenge: &StepUpChallenge, phishing_resistant: bool) -> f64 {
+        let bonus = match challenge.challenge_type {
             ChallengeType::Biometric => 15.0,
             ChallengeType::Mfa => 10.0,
             ChallengeType::ReAuth => 20.0,
             ChallengeType::ManagerApproval => 5.0,
             ChallengeType::Custom => 5.0,
-        }
+            ChallengeType::DeviceAttestation => 15.0,
+        };
+        if phishing_resistant { bonus + 5.0 } else { bonus }
     }
     
     /// Cancel challenge
@@ -238,6 +584,8 @@ pub struct StepUpResult {
     pub session_id: String,
     pub verified: bool,
     pub trust_bonus: f64,
+    /// Satisfied with a phishing-resistant factor
+    pub phishing_resistant: bool,
 }
 
 #[derive(Debug)]
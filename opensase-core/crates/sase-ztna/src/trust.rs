@@ -131,7 +131,7 @@ impl TrustEvaluationEngine {
     }
     
     fn evaluate_identity(&self, identity: &Identity, factors: &mut Vec<TrustFactor>) -> f64 {
-        let mut score = 50.0; // Base score
+        let mut score: f64 = 50.0; // Base score
         
         // MFA verified
         if identity.mfa_verified {
@@ -179,7 +179,7 @@ impl TrustEvaluationEngine {
     }
     
     fn evaluate_device(&self, device: &Device, factors: &mut Vec<TrustFactor>) -> f64 {
-        let mut score = 0.0;
+        let mut score: f64 = 0.0;
         
         // Managed device
         if device.managed {
@@ -256,7 +256,7 @@ impl TrustEvaluationEngine {
     }
     
     fn evaluate_context(&self, context: &AccessContext, factors: &mut Vec<TrustFactor>) -> f64 {
-        let mut score = 70.0; // Base score
+        let mut score: f64 = 70.0; // Base score
         
         // Network type
         match context.network_type {
@@ -3,6 +3,7 @@
 //! Continuous trust evaluation with behavioral analysis.
 
 use crate::{Identity, Device, AccessContext, TrustLevel, RiskSignal, RiskSeverity};
+use crate::pki::DeviceCertificateState;
 use std::collections::HashMap;
 
 /// Continuous trust evaluation engine
@@ -29,6 +30,8 @@ pub struct TrustContext {
     pub device_id: String,
     pub device_posture: EnhancedDevicePosture,
     pub device_trust_level: TrustLevel,
+    /// Client certificate checked by the device CA
+    pub device_certificate: DeviceCertificateState,
     
     // Context
     pub source_ip: std::net::IpAddr,
@@ -185,7 +188,7 @@ impl EnhancedTrustEngine {
     }
     
     async fn evaluate_identity(&self, context: &TrustContext, factors: &mut Vec<TrustFactor>) -> f64 {
-        let mut score = 50.0;
+        let mut score: f64 = 50.0;
         
         // Authentication method strength
         let auth_impact = match context.authentication_method {
@@ -275,7 +278,7 @@ impl EnhancedTrustEngine {
     
     async fn evaluate_device(&self, context: &TrustContext, factors: &mut Vec<TrustFactor>) -> f64 {
         let posture = &context.device_posture;
-        let mut score = 50.0;
+        let mut score: f64 = 50.0;
         
         // Management status
         match posture.management_status {
@@ -353,6 +356,33 @@ impl EnhancedTrustEngine {
             score -= 10.0;
         }
         
+        // Device identity: a certificate from the device CA proves which
+        // device this is; the posture above is only what it reports
+        let (name, impact, description) = match &context.device_certificate {
+            DeviceCertificateState::Verified(identity) if identity.device_id == context.device_id => {
+                ("device_certificate", 20.0, format!("Device certificate {} verified", identity.serial))
+            }
+            DeviceCertificateState::Verified(identity) => (
+                "certificate_device_mismatch",
+                -30.0,
+                format!("Certificate belongs to device {}", identity.device_id),
+            ),
+            DeviceCertificateState::Missing => ("no_device_certificate", -10.0, "No device certificate presented".to_string()),
+            DeviceCertificateState::Invalid(reason) => ("invalid_device_certificate", -25.0, reason.clone()),
+            DeviceCertificateState::Revoked(reason) => (
+                "revoked_device_certificate",
+                -50.0,
+                format!("Device certificate revoked ({:?})", reason),
+            ),
+        };
+        factors.push(TrustFactor {
+            name: name.to_string(),
+            category: TrustCategory::Device,
+            score_impact: impact,
+            description,
+        });
+        score += impact;
+        
         // Jailbreak/root
         if posture.is_jailbroken || posture.is_rooted {
             factors.push(TrustFactor {
@@ -373,7 +403,7 @@ impl EnhancedTrustEngine {
     }
     
     async fn evaluate_context(&self, context: &TrustContext, factors: &mut Vec<TrustFactor>) -> f64 {
-        let mut score = 70.0;
+        let mut score: f64 = 70.0;
         
         // Network type
         match context.network_type {
@@ -441,7 +471,7 @@ impl EnhancedTrustEngine {
     }
     
     async fn evaluate_behavior(&self, context: &TrustContext, factors: &mut Vec<TrustFactor>) -> f64 {
-        let mut score = 80.0;
+        let mut score: f64 = 80.0;
         
         // Get user baseline
         let baseline = self.behavior_analyzer.user_baselines
@@ -0,0 +1,289 @@
+//! Risk Watchlist
+//!
+//! Users and hosts the SOC's entity analytics flagged as high risk. The
+//! SOC pushes watchlist changes (see `sase_soc::ueba`) to the gateway; a
+//! watched entity's requests carry extra risk and need MFA, phishing-
+//! resistant MFA when critical, and its live sessions are re-scored by
+//! continuous evaluation.
+
+use crate::continuous::SessionSignalSource;
+use crate::{AccessCondition, Device, RiskSeverity, RiskSignal, RiskSignalType, Session};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchLevel {
+    Elevated,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    User,
+    /// Matched against the device id or name
+    Host,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchedEntity {
+    pub kind: EntityKind,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistEntry {
+    pub entity: WatchedEntity,
+    pub level: WatchLevel,
+    /// Entity risk score (0-100) when the entry was raised
+    pub score: f64,
+    pub reason: String,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A change pushed by the SOC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum WatchlistUpdate {
+    Added { entry: WatchlistEntry },
+    Changed { entry: WatchlistEntry, previous: WatchLevel },
+    Removed { entity: WatchedEntity, reason: String },
+}
+
+/// How much a watchlist entry tightens access
+#[derive(Debug, Clone)]
+pub struct WatchlistEnforcement {
+    /// Added to the request risk score for elevated entities
+    pub elevated_risk: f64,
+    /// Added to the request risk score for critical entities
+    pub critical_risk: f64,
+    /// Add an MFA condition to granted access
+    pub require_mfa: bool,
+}
+
+impl Default for WatchlistEnforcement {
+    fn default() -> Self {
+        Self {
+            elevated_risk: 20.0,
+            critical_risk: 40.0,
+            require_mfa: true,
+        }
+    }
+}
+
+/// Watch level that applies to a request, and why
+#[derive(Debug, Clone)]
+pub struct WatchMatch {
+    pub level: WatchLevel,
+    pub reason: String,
+}
+
+pub struct Watchlist {
+    users: dashmap::DashMap<String, WatchlistEntry>,
+    hosts: dashmap::DashMap<String, WatchlistEntry>,
+    enforcement: WatchlistEnforcement,
+}
+
+impl Watchlist {
+    pub fn new() -> Self {
+        Self {
+            users: dashmap::DashMap::new(),
+            hosts: dashmap::DashMap::new(),
+            enforcement: WatchlistEnforcement::default(),
+        }
+    }
+
+    pub fn with_enforcement(mut self, enforcement: WatchlistEnforcement) -> Self {
+        self.enforcement = enforcement;
+        self
+    }
+
+    fn entries_for(&self, kind: EntityKind) -> &dashmap::DashMap<String, WatchlistEntry> {
+        match kind {
+            EntityKind::User => &self.users,
+            EntityKind::Host => &self.hosts,
+        }
+    }
+
+    pub fn apply(&self, update: WatchlistUpdate) {
+        match update {
+            WatchlistUpdate::Added { entry } | WatchlistUpdate::Changed { entry, .. } => {
+                tracing::info!("Watchlist: {:?} {} is {:?} ({})", entry.entity.kind, entry.entity.id, entry.level, entry.reason);
+                self.entries_for(entry.entity.kind).insert(entry.entity.id.clone(), entry);
+            }
+            WatchlistUpdate::Removed { entity, reason } => {
+                tracing::info!("Watchlist: {:?} {} released ({})", entity.kind, entity.id, reason);
+                self.entries_for(entity.kind).remove(&entity.id);
+            }
+        }
+    }
+
+    /// Apply a change posted by the SOC's watchlist webhook
+    pub fn apply_json(&self, body: &[u8]) -> Result<(), serde_json::Error> {
+        self.apply(serde_json::from_slice(body)?);
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<WatchlistEntry> {
+        self.users.iter().chain(self.hosts.iter()).map(|e| e.clone()).collect()
+    }
+
+    /// Highest watch level of the user and the device
+    pub fn check(&self, user_id: &str, device: &Device) -> Option<WatchMatch> {
+        let user = self.users.get(user_id).map(|e| e.clone());
+        let host = self.hosts.get(&device.id)
+            .or_else(|| self.hosts.get(&device.name))
+            .map(|e| e.clone());
+        let entry = match (user, host) {
+            (Some(user), Some(host)) => if host.level > user.level { host } else { user },
+            (user, host) => user.or(host)?,
+        };
+        let subject = match entry.entity.kind {
+            EntityKind::User => "user",
+            EntityKind::Host => "device",
+        };
+        Some(WatchMatch {
+            level: entry.level,
+            reason: format!("{} on {:?} watchlist: {}", subject, entry.level, entry.reason),
+        })
+    }
+
+    /// Risk added to a request by a watch match
+    pub fn risk_for(&self, watch: &WatchMatch) -> f64 {
+        match watch.level {
+            WatchLevel::Elevated => self.enforcement.elevated_risk,
+            WatchLevel::Critical => self.enforcement.critical_risk,
+        }
+    }
+
+    /// Condition added to granted access, if any
+    pub fn condition_for(&self, watch: &WatchMatch) -> Option<AccessCondition> {
+        if !self.enforcement.require_mfa {
+            return None;
+        }
+        Some(match watch.level {
+            WatchLevel::Elevated => AccessCondition::RequireMfa,
+            WatchLevel::Critical => AccessCondition::RequirePhishingResistantMfa,
+        })
+    }
+}
+
+impl Default for Watchlist {
+    fn default() -> Self { Self::new() }
+}
+
+/// Watched sessions are re-scored with a signal for their watch level
+#[async_trait::async_trait]
+impl SessionSignalSource for Watchlist {
+    async fn signals(&self, session: &Session) -> Vec<RiskSignal> {
+        let Some(watch) = self.check(&session.identity.user_id, &session.device) else {
+            return Vec::new();
+        };
+        vec![RiskSignal {
+            signal_type: RiskSignalType::UnusualBehavior,
+            severity: match watch.level {
+                WatchLevel::Elevated => RiskSeverity::Medium,
+                WatchLevel::Critical => RiskSeverity::High,
+            },
+            description: watch.reason,
+            detected_at: Utc::now(),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str, name: &str) -> Device {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "device_type": "Laptop",
+            "os": "macOS",
+            "os_version": "14.0",
+            "managed": true,
+            "compliant": true,
+            "trust_level": "High",
+            "posture": {
+                "firewall_enabled": true,
+                "antivirus_running": true,
+                "disk_encrypted": true,
+                "os_patched": true,
+                "screen_lock_enabled": true,
+                "jailbroken": false,
+                "last_checked": Utc::now(),
+            },
+            "certificates": [],
+            "last_seen": Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    /// A change as the SOC's watchlist webhook posts it
+    fn update(change: &str, kind: &str, id: &str, level: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "change": change,
+            "entry": {
+                "entity": { "kind": kind, "id": id },
+                "level": level,
+                "score": 72.5,
+                "reason": "Critical alert: BruteForce",
+                "added_by": "system",
+                "hold_until": Utc::now(),
+                "manual": false,
+            },
+            "previous": "elevated",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply_soc_updates() {
+        let watchlist = Watchlist::new();
+        watchlist.apply_json(&update("added", "user", "alice", "elevated")).unwrap();
+        watchlist.apply_json(&update("changed", "host", "laptop-7", "critical")).unwrap();
+        assert_eq!(watchlist.entries().len(), 2);
+
+        let removed = serde_json::json!({
+            "change": "removed",
+            "entity": { "kind": "user", "id": "alice" },
+            "reason": "score fell to 12.0",
+        });
+        watchlist.apply_json(removed.to_string().as_bytes()).unwrap();
+        assert_eq!(watchlist.entries().len(), 1);
+
+        assert!(watchlist.apply_json(b"{\"change\":\"renamed\"}").is_err());
+    }
+
+    #[test]
+    fn test_check_takes_highest_level() {
+        let watchlist = Watchlist::new();
+        assert!(watchlist.check("alice", &device("d1", "laptop-7")).is_none());
+
+        watchlist.apply_json(&update("added", "user", "alice", "elevated")).unwrap();
+        // Hosts match by device id or name
+        watchlist.apply_json(&update("added", "host", "laptop-7", "critical")).unwrap();
+
+        let watch = watchlist.check("alice", &device("d1", "laptop-7")).unwrap();
+        assert_eq!(watch.level, WatchLevel::Critical);
+        assert!(watch.reason.starts_with("device on Critical watchlist"));
+        assert_eq!(watchlist.risk_for(&watch), 40.0);
+        assert!(matches!(watchlist.condition_for(&watch), Some(AccessCondition::RequirePhishingResistantMfa)));
+
+        let watch = watchlist.check("alice", &device("d2", "desktop")).unwrap();
+        assert_eq!(watch.level, WatchLevel::Elevated);
+        assert!(matches!(watchlist.condition_for(&watch), Some(AccessCondition::RequireMfa)));
+        assert!(watchlist.check("bob", &device("d2", "desktop")).is_none());
+
+        let lenient = Watchlist::new().with_enforcement(WatchlistEnforcement {
+            elevated_risk: 5.0,
+            critical_risk: 10.0,
+            require_mfa: false,
+        });
+        assert_eq!(lenient.risk_for(&watch), 5.0);
+        assert!(lenient.condition_for(&watch).is_none());
+    }
+}